@@ -2,7 +2,7 @@
 // Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
 // Details of functionality of this file: Hash chain implementation - Merkle-style hash chaining for tamper-proof audit logs
 
-use sha2::{Sha256, Digest};
+use crypto::digest::Sha256;
 use hex;
 use std::sync::Arc;
 use parking_lot::RwLock;
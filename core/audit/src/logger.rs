@@ -43,9 +43,19 @@ impl AuditLogger {
         
         let writer = BufWriter::new(file);
         
+        // Continue the chain of an existing log instead of starting a second genesis
+        let chain = HashChain::new();
+        let existing = std::fs::read_to_string(path)
+            .map_err(|e| AuditError::IoError(e))?;
+        if let Some(last_line) = existing.lines().rev().find(|l| !l.trim().is_empty()) {
+            let last: AuditRecord = serde_json::from_str(last_line)
+                .map_err(|e| AuditError::TamperingDetected(format!("Unreadable last audit record: {}", e)))?;
+            chain.set_previous_hash(last.hash);
+        }
+        
         Ok(Self {
             log_path: path.to_string_lossy().to_string(),
-            chain: HashChain::new(),
+            chain,
             signer,
             clock: ClockGuard::new(),
             writer: Some(writer),
@@ -103,7 +113,8 @@ impl AuditLogger {
         let record_with_hash_json = serde_json::to_string(&record)
             .map_err(|e| AuditError::SerializationError(format!("Failed to serialize record with hash: {}", e)))?;
         
-        let signature = self.signer.sign(record_with_hash_json.as_bytes());
+        // FAIL-CLOSED: an unavailable signing key (HSM token gone) means no record is written
+        let signature = self.signer.sign(record_with_hash_json.as_bytes())?;
         record.signature = signature;
         
         // Write to log (append-only)
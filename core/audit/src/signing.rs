@@ -1,47 +1,134 @@
 // Path and File Name : /home/ransomeye/rebuild/core/audit/src/signing.rs
 // Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
-// Details of functionality of this file: Audit signing - Ed25519 signature generation and verification
+// Details of functionality of this file: Audit signing - Ed25519 signature generation and verification, with a local key file or a PKCS#11 (HSM) held key
 
-use ed25519_dalek::{SigningKey, VerifyingKey, Signature, Signer, Verifier};
-use rand::rngs::OsRng;
-use rand::RngCore;
+use crypto::pkcs11::Pkcs11Config;
+use crypto::signature::{verify_ed25519, Ed25519KeyPair, ED25519_PUBLIC_KEY_LEN};
 use hex;
 use base64::{Engine as _, engine::general_purpose};
+use std::path::Path;
 
-/// Audit signer using Ed25519
+use crate::errors::AuditError;
+
+/// Env prefix for an HSM-held audit key (`RANSOMEYE_AUDIT_PKCS11_MODULE`, ...)
+pub const AUDIT_PKCS11_ENV_PREFIX: &str = "RANSOMEYE_AUDIT_PKCS11";
+
+enum SigningKey {
+    Local(Ed25519KeyPair),
+    #[cfg(feature = "pkcs11")]
+    Hsm(crypto::pkcs11::Pkcs11Key),
+}
+
+/// Audit signer using Ed25519 (through the build's crypto backend, or a PKCS#11 token)
 pub struct AuditSigner {
-    signing_key: SigningKey,
-    verifying_key: VerifyingKey,
+    key: SigningKey,
+    public_key: [u8; ED25519_PUBLIC_KEY_LEN],
 }
 
 impl AuditSigner {
     /// Create new audit signer (generates new keypair)
     pub fn new() -> Self {
-        let mut csprng = OsRng;
-        let mut key_bytes = [0u8; 32];
-        csprng.fill_bytes(&mut key_bytes);
-        let signing_key = SigningKey::from_bytes(&key_bytes);
-        let verifying_key = signing_key.verifying_key();
+        let mut seed = [0u8; 32];
+        crypto::rand::fill(&mut seed).expect("crypto backend random generator failed");
+        Self::from_seed(&seed).expect("crypto backend rejected a fresh Ed25519 seed")
+    }
+    
+    /// Create from an existing 32-byte Ed25519 seed
+    pub fn from_seed(seed: &[u8; 32]) -> Result<Self, AuditError> {
+        let key_pair = Ed25519KeyPair::from_seed(seed)
+            .map_err(|e| AuditError::SignatureFailed(format!("Invalid audit key: {}", e)))?;
+        let public_key = key_pair.public_key();
+        Ok(Self {
+            key: SigningKey::Local(key_pair),
+            public_key,
+        })
+    }
+    
+    /// Load the signing key (32 raw bytes) from `path`, generating it on first use
+    /// A generated key is written owner-read-only
+    pub fn from_key_file(path: impl AsRef<Path>) -> Result<Self, AuditError> {
+        let path = path.as_ref();
+        if path.exists() {
+            let key_data = std::fs::read(path)?;
+            let key_bytes: [u8; 32] = key_data.as_slice().try_into()
+                .map_err(|_| AuditError::SerializationError(
+                    format!("Invalid audit key length in {}: expected 32, got {}", path.display(), key_data.len())
+                ))?;
+            return Self::from_seed(&key_bytes);
+        }
         
-        Self {
-            signing_key,
-            verifying_key,
+        let mut seed = [0u8; 32];
+        crypto::rand::fill(&mut seed)
+            .map_err(|e| AuditError::SignatureFailed(format!("Failed to generate audit key: {}", e)))?;
+        let signer = Self::from_seed(&seed)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
         }
+        use std::io::Write;
+        options.open(path)?.write_all(&seed)?;
+        Ok(signer)
     }
     
-    /// Create from existing keypair
-    pub fn from_keypair(signing_key: SigningKey) -> Self {
-        let verifying_key = signing_key.verifying_key();
-        Self {
-            signing_key,
-            verifying_key,
+    /// Use the Ed25519 key `config` selects on a PKCS#11 token. FAIL-CLOSED: an unavailable
+    /// token or a non-Ed25519 key is an error, never a fallback to a local key.
+    #[cfg(feature = "pkcs11")]
+    pub fn from_pkcs11(config: &Pkcs11Config) -> Result<Self, AuditError> {
+        use crypto::pkcs11::{Pkcs11Key, Pkcs11KeyKind};
+        
+        let key = Pkcs11Key::open(config)
+            .map_err(|e| AuditError::SignatureFailed(format!("Audit signing key unavailable: {}", e)))?;
+        if key.kind() != Pkcs11KeyKind::Ed25519 {
+            return Err(AuditError::SignatureFailed(format!(
+                "Audit key '{}' is {:?}, expected Ed25519", config.key_label, key.kind()
+            )));
         }
+        let public_key: [u8; ED25519_PUBLIC_KEY_LEN] = key.public_key().try_into()
+            .map_err(|_| AuditError::SignatureFailed("Invalid Ed25519 public key from token".to_string()))?;
+        Ok(Self {
+            key: SigningKey::Hsm(key),
+            public_key,
+        })
     }
     
-    /// Sign audit record data
-    pub fn sign(&self, data: &[u8]) -> String {
-        let signature: Signature = self.signing_key.sign(data);
-        general_purpose::STANDARD.encode(signature.to_bytes())
+    #[cfg(not(feature = "pkcs11"))]
+    pub fn from_pkcs11(config: &Pkcs11Config) -> Result<Self, AuditError> {
+        Err(AuditError::SignatureFailed(format!(
+            "Audit key '{}' is configured on a PKCS#11 token but this build lacks the pkcs11 feature",
+            config.key_label
+        )))
+    }
+    
+    /// HSM key when `RANSOMEYE_AUDIT_PKCS11_*` is configured, otherwise the key file at `path`
+    pub fn from_env_or_key_file(path: impl AsRef<Path>) -> Result<Self, AuditError> {
+        let config = Pkcs11Config::from_env(AUDIT_PKCS11_ENV_PREFIX)
+            .map_err(|e| AuditError::SignatureFailed(e.to_string()))?;
+        match config {
+            Some(config) => Self::from_pkcs11(&config),
+            None => Self::from_key_file(path),
+        }
+    }
+    
+    /// Sign audit record data. Fails only for HSM keys whose token is gone (after one reconnect).
+    pub fn sign(&self, data: &[u8]) -> Result<String, AuditError> {
+        let signature = match &self.key {
+            SigningKey::Local(key_pair) => key_pair.sign(data).to_vec(),
+            #[cfg(feature = "pkcs11")]
+            SigningKey::Hsm(key) => key.sign(data)
+                .map_err(|e| AuditError::SignatureFailed(format!("HSM signing failed: {}", e)))?,
+        };
+        Ok(general_purpose::STANDARD.encode(signature))
+    }
+    
+    /// Whether the key is held in a PKCS#11 token
+    pub fn is_hsm_backed(&self) -> bool {
+        !matches!(self.key, SigningKey::Local(_))
     }
     
     /// Verify signature
@@ -53,11 +140,7 @@ impl AuditSigner {
             return Err(format!("Invalid signature length: expected 64, got {}", signature_bytes.len()));
         }
         
-        let mut sig_array = [0u8; 64];
-        sig_array.copy_from_slice(&signature_bytes[..64]);
-        let signature = Signature::from_bytes(&sig_array);
-        
-        self.verifying_key.verify(data, &signature)
+        verify_ed25519(&self.public_key, data, &signature_bytes)
             .map_err(|e| format!("Signature verification failed: {}", e))?;
         
         Ok(())
@@ -65,7 +148,7 @@ impl AuditSigner {
     
     /// Get verifying key (public key) as hex
     pub fn get_verifying_key_hex(&self) -> String {
-        hex::encode(self.verifying_key.to_bytes())
+        hex::encode(self.public_key)
     }
 }
 
@@ -10,7 +10,7 @@ use tracing::{error, warn, info};
 use crate::errors::AuditError;
 use crate::chain::{HashChain, AuditRecord};
 use crate::signing::AuditSigner;
-use ed25519_dalek::{VerifyingKey, Verifier, Signature};
+use crypto::signature::verify_ed25519;
 use hex;
 use base64::{Engine as _, engine::general_purpose};
 
@@ -88,11 +88,6 @@ impl AuditVerifier {
             ));
         }
         
-        let mut key_array = [0u8; 32];
-        key_array.copy_from_slice(&verifying_key_bytes[..32]);
-        let verifying_key = VerifyingKey::from_bytes(&key_array)
-            .map_err(|e| AuditError::SerializationError(format!("Invalid verifying key: {}", e)))?;
-        
         for (i, record) in records.iter().enumerate() {
             // Reconstruct signed data (record without signature)
             let mut record_for_signing = record.clone();
@@ -109,11 +104,7 @@ impl AuditVerifier {
                 continue;
             }
             
-            let mut sig_array = [0u8; 64];
-            sig_array.copy_from_slice(&signature_bytes[..64]);
-            let signature = Signature::from_bytes(&sig_array);
-            
-            if verifying_key.verify(record_json.as_bytes(), &signature).is_err() {
+            if verify_ed25519(&verifying_key_bytes, record_json.as_bytes(), &signature_bytes).is_err() {
                 errors.push(format!("Signature verification failed for record {}", i));
             }
         }
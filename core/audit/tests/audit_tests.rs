@@ -248,3 +248,27 @@ fn test_hash_chain_integrity() {
     assert!(result.is_ok() && result.unwrap(), "Hash chain should be valid");
 }
 
+
+#[test]
+fn test_reopened_log_continues_chain() {
+    let temp_dir = TempDir::new().unwrap();
+    let log_path = temp_dir.path().join("audit.log");
+    let key_path = temp_dir.path().join("audit.key");
+    
+    let mut logger = AuditLogger::new(&log_path, AuditSigner::from_key_file(&key_path).unwrap()).unwrap();
+    logger.log("test_component", "first", "test_actor", "test_host", serde_json::json!({})).unwrap();
+    let verifying_key = logger.get_verifying_key_hex();
+    drop(logger);
+    
+    // Same key is loaded again and the second record links to the first
+    let signer = AuditSigner::from_key_file(&key_path).unwrap();
+    assert_eq!(signer.get_verifying_key_hex(), verifying_key);
+    let mut logger = AuditLogger::new(&log_path, signer).unwrap();
+    logger.log("test_component", "second", "test_actor", "test_host", serde_json::json!({})).unwrap();
+    drop(logger);
+    
+    let content = std::fs::read_to_string(&log_path).unwrap();
+    let records: Vec<serde_json::Value> = content.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[1]["previous_hash"], records[0]["hash"]);
+}
@@ -0,0 +1,274 @@
+// Path and File Name : /home/ransomeye/rebuild/core/build_info/src/emit.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Build-script half of build provenance - resolves the git commit, hashes Cargo.lock, writes a deterministic CycloneDX SBOM into OUT_DIR and hands all three to the compiler as env vars
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde_json::{json, Value as JsonValue};
+
+use crate::{
+    sha256_hex, ENV_CARGO_LOCK_SHA256, ENV_GIT_COMMIT, ENV_SBOM_SHA256, SBOM_FILE, UNKNOWN_COMMIT,
+};
+
+/// Overrides `git rev-parse HEAD` (CI checkouts without .git, source tarballs)
+pub const COMMIT_OVERRIDE_ENV: &str = "RANSOMEYE_BUILD_COMMIT";
+
+/// One `[[package]]` entry of Cargo.lock.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LockedPackage {
+    pub name: String,
+    pub version: String,
+    /// `registry+...` / `git+...`; None for workspace and path packages
+    pub source: Option<String>,
+    /// SHA-256 of the downloaded .crate (registry packages only)
+    pub checksum: Option<String>,
+}
+
+/// Entry point for build.rs. Panics (fails the build) when Cargo.lock cannot be found or the SBOM
+/// cannot be written: a binary without provenance is never produced.
+pub fn emit() {
+    let manifest_dir = PathBuf::from(env_required("CARGO_MANIFEST_DIR"));
+    let out_dir = PathBuf::from(env_required("OUT_DIR"));
+    let package = env_required("CARGO_PKG_NAME");
+    let version = env_required("CARGO_PKG_VERSION");
+
+    let lock_path = find_lockfile(&manifest_dir)
+        .unwrap_or_else(|| panic!("build provenance: no Cargo.lock above {}", manifest_dir.display()));
+    let lock = std::fs::read_to_string(&lock_path)
+        .unwrap_or_else(|e| panic!("build provenance: cannot read {}: {e}", lock_path.display()));
+    let lock_sha256 = sha256_hex(lock.as_bytes());
+    let commit = git_commit(&manifest_dir);
+
+    let sbom = cyclonedx_sbom(&package, &version, &commit, &lock_sha256, &parse_lockfile(&lock));
+    let sbom_bytes = serde_json::to_vec_pretty(&sbom).expect("SBOM serializes");
+    let sbom_sha256 = sha256_hex(&sbom_bytes);
+    let sbom_path = out_dir.join(SBOM_FILE);
+    std::fs::write(&sbom_path, &sbom_bytes)
+        .unwrap_or_else(|e| panic!("build provenance: cannot write {}: {e}", sbom_path.display()));
+
+    println!("cargo:rerun-if-changed={}", lock_path.display());
+    println!("cargo:rerun-if-env-changed={COMMIT_OVERRIDE_ENV}");
+    for path in git_watch_paths(&manifest_dir) {
+        println!("cargo:rerun-if-changed={}", path.display());
+    }
+    println!("cargo:rustc-env={ENV_GIT_COMMIT}={commit}");
+    println!("cargo:rustc-env={ENV_CARGO_LOCK_SHA256}={lock_sha256}");
+    println!("cargo:rustc-env={ENV_SBOM_SHA256}={sbom_sha256}");
+}
+
+fn env_required(key: &str) -> String {
+    std::env::var(key).unwrap_or_else(|_| panic!("build provenance: {key} not set (not running under cargo?)"))
+}
+
+/// Nearest Cargo.lock at or above the crate (workspace root for workspace members).
+pub fn find_lockfile(start: &Path) -> Option<PathBuf> {
+    start.ancestors().map(|dir| dir.join("Cargo.lock")).find(|p| p.is_file())
+}
+
+fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    let out = Command::new("git").args(args).current_dir(dir).output().ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    let s = String::from_utf8(out.stdout).ok()?.trim().to_string();
+    (!s.is_empty()).then_some(s)
+}
+
+fn is_commit(s: &str) -> bool {
+    s.len() == 40 && s.bytes().all(|b| b.is_ascii_hexdigit() && !b.is_ascii_uppercase())
+}
+
+/// RANSOMEYE_BUILD_COMMIT, else HEAD of the enclosing checkout, else "unknown".
+fn git_commit(dir: &Path) -> String {
+    if let Ok(c) = std::env::var(COMMIT_OVERRIDE_ENV) {
+        let c = c.trim().to_ascii_lowercase();
+        if !is_commit(&c) {
+            panic!("build provenance: {COMMIT_OVERRIDE_ENV}='{c}' is not a 40-hex commit id");
+        }
+        return c;
+    }
+    git(dir, &["rev-parse", "HEAD"])
+        .filter(|c| is_commit(c))
+        .unwrap_or_else(|| UNKNOWN_COMMIT.to_string())
+}
+
+/// HEAD, the branch ref it points at and packed-refs: a new commit or checkout re-runs the script.
+fn git_watch_paths(dir: &Path) -> Vec<PathBuf> {
+    let mut refs = vec!["HEAD".to_string(), "packed-refs".to_string()];
+    if let Some(r) = git(dir, &["symbolic-ref", "-q", "HEAD"]) {
+        refs.push(r);
+    }
+    refs.iter()
+        .filter_map(|r| git(dir, &["rev-parse", "--path-format=absolute", "--git-path", r]))
+        .map(PathBuf::from)
+        .filter(|p| p.exists())
+        .collect()
+}
+
+/// Minimal Cargo.lock reader: `[[package]]` tables with name/version/source/checksum; sorted, deduplicated.
+pub fn parse_lockfile(lock: &str) -> Vec<LockedPackage> {
+    fn flush(current: &mut Option<LockedPackage>, out: &mut BTreeSet<LockedPackage>) {
+        if let Some(p) = current.take() {
+            if !p.name.is_empty() && !p.version.is_empty() {
+                out.insert(p);
+            }
+        }
+    }
+    let mut out = BTreeSet::new();
+    let mut current: Option<LockedPackage> = None;
+    for line in lock.lines().map(str::trim) {
+        if line.starts_with('[') {
+            flush(&mut current, &mut out);
+            if line == "[[package]]" {
+                current = Some(LockedPackage { name: String::new(), version: String::new(), source: None, checksum: None });
+            }
+            continue;
+        }
+        let (Some(pkg), Some((key, value))) = (current.as_mut(), line.split_once(" = ")) else {
+            continue;
+        };
+        let Some(value) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) else {
+            continue;
+        };
+        match key {
+            "name" => pkg.name = value.to_string(),
+            "version" => pkg.version = value.to_string(),
+            "source" => pkg.source = Some(value.to_string()),
+            "checksum" => pkg.checksum = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    flush(&mut current, &mut out);
+    out.into_iter().collect()
+}
+
+fn purl(name: &str, version: &str) -> String {
+    format!("pkg:cargo/{name}@{version}")
+}
+
+/// CycloneDX 1.5 document for `package` listing every locked package except itself.
+///
+/// No timestamp, serial number derived from the inputs, keys and components in sorted order: the
+/// same commit and lockfile always give the same bytes.
+pub fn cyclonedx_sbom(
+    package: &str,
+    version: &str,
+    commit: &str,
+    lock_sha256: &str,
+    locked: &[LockedPackage],
+) -> JsonValue {
+    let components: Vec<JsonValue> = locked
+        .iter()
+        .filter(|p| !(p.name == package && p.version == version && p.source.is_none()))
+        .map(|p| {
+            let mut c = json!({
+                "type": "library",
+                "bom-ref": purl(&p.name, &p.version),
+                "name": p.name,
+                "version": p.version,
+                "purl": purl(&p.name, &p.version),
+            });
+            if let Some(sum) = &p.checksum {
+                c["hashes"] = json!([{ "alg": "SHA-256", "content": sum }]);
+            }
+            if let Some(src) = &p.source {
+                c["properties"] = json!([{ "name": "cargo:source", "value": src }]);
+            }
+            c
+        })
+        .collect();
+
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "serialNumber": serial_number(package, version, commit, lock_sha256),
+        "version": 1,
+        "metadata": {
+            "component": {
+                "type": "application",
+                "bom-ref": purl(package, version),
+                "name": package,
+                "version": version,
+                "purl": purl(package, version),
+            },
+            "properties": [
+                { "name": "ransomeye:git_commit", "value": commit },
+                { "name": "ransomeye:cargo_lock_sha256", "value": lock_sha256 },
+            ],
+        },
+        "components": components,
+    })
+}
+
+/// `urn:uuid:` with a UUID-shaped (version 8) digest of the inputs instead of a random one.
+fn serial_number(package: &str, version: &str, commit: &str, lock_sha256: &str) -> String {
+    let digest = crypto::digest::Sha256::new()
+        .chain_update(format!("{package}\n{version}\n{commit}\n{lock_sha256}"))
+        .finalize();
+    let mut b = [0u8; 16];
+    b.copy_from_slice(&digest[..16]);
+    b[6] = (b[6] & 0x0f) | 0x80;
+    b[8] = (b[8] & 0x3f) | 0x80;
+    let h = hex::encode(b);
+    format!("urn:uuid:{}-{}-{}-{}-{}", &h[0..8], &h[8..12], &h[12..16], &h[16..20], &h[20..32])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOCK: &str = r#"
+# This file is automatically @generated by Cargo.
+version = 3
+
+[[package]]
+name = "serde"
+version = "1.0.200"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "ddc6f9cc94d67c0e21aaf7eda3a010fd3af78ebf6e096aa6e2e13c79749cce4f"
+dependencies = [
+ "serde_derive",
+]
+
+[[package]]
+name = "engine"
+version = "1.0.0"
+dependencies = [
+ "serde",
+]
+
+[[package]]
+name = "crypto"
+version = "1.0.0"
+"#;
+
+    #[test]
+    fn test_lockfile_packages_sorted_with_checksums() {
+        let pkgs = parse_lockfile(LOCK);
+        let names: Vec<&str> = pkgs.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["crypto", "engine", "serde"]);
+        assert!(pkgs[0].source.is_none() && pkgs[0].checksum.is_none());
+        assert_eq!(pkgs[2].checksum.as_deref().map(str::len), Some(64));
+    }
+
+    #[test]
+    fn test_sbom_is_deterministic_and_excludes_itself() {
+        let pkgs = parse_lockfile(LOCK);
+        let commit = "0123456789abcdef0123456789abcdef01234567";
+        let a = cyclonedx_sbom("engine", "1.0.0", commit, "aa", &pkgs);
+        let b = cyclonedx_sbom("engine", "1.0.0", commit, "aa", &pkgs);
+        assert_eq!(serde_json::to_vec(&a).unwrap(), serde_json::to_vec(&b).unwrap());
+
+        let refs: Vec<&str> = a["components"].as_array().unwrap().iter().map(|c| c["bom-ref"].as_str().unwrap()).collect();
+        assert_eq!(refs, vec!["pkg:cargo/crypto@1.0.0", "pkg:cargo/serde@1.0.200"]);
+        assert_eq!(a["components"][1]["hashes"][0]["alg"], "SHA-256");
+        assert_eq!(a["metadata"]["component"]["purl"], "pkg:cargo/engine@1.0.0");
+
+        let serial = a["serialNumber"].as_str().unwrap();
+        assert!(serial.starts_with("urn:uuid:") && serial.len() == 45);
+        let other = cyclonedx_sbom("engine", "1.0.0", commit, "bb", &pkgs);
+        assert_ne!(other["serialNumber"], a["serialNumber"]);
+    }
+}
@@ -0,0 +1,153 @@
+// Path and File Name : /home/ransomeye/rebuild/core/build_info/src/lib.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Build provenance embedded in every RansomEye binary - git commit, Cargo.lock hash and CycloneDX SBOM captured by build.rs, printed by --version [--json], recorded on the components row and checked against the release manifest at startup
+
+//! Each binary crate calls [`emit`] from its build.rs and declares
+//! `static BUILD_INFO: build_info::BuildInfo = build_info::build_info!();`. Nothing in the embedded
+//! data depends on the build time or machine, so a rebuild from the same commit and lockfile embeds
+//! identical bytes.
+//!
+//! At startup a binary calls [`BuildInfo::handle_version_flag`] (`--version`, `--version --json`)
+//! and then [`BuildInfo::verify_release_from_env`]: when RANSOMEYE_RELEASE_MANIFEST names a release
+//! manifest, the embedded metadata and the hash of the running executable must match it or the
+//! binary refuses to start.
+
+mod emit;
+pub mod release;
+
+pub use emit::{cyclonedx_sbom, emit, find_lockfile, parse_lockfile, LockedPackage, COMMIT_OVERRIDE_ENV};
+pub use release::{ReleaseComponent, ReleaseManifest, RELEASE_MANIFEST_ENV};
+
+use std::io::Write;
+use std::path::Path;
+
+use serde_json::{json, Value as JsonValue};
+use thiserror::Error;
+
+#[doc(hidden)]
+pub const ENV_GIT_COMMIT: &str = "RANSOMEYE_BUILD_GIT_COMMIT";
+#[doc(hidden)]
+pub const ENV_CARGO_LOCK_SHA256: &str = "RANSOMEYE_BUILD_CARGO_LOCK_SHA256";
+#[doc(hidden)]
+pub const ENV_SBOM_SHA256: &str = "RANSOMEYE_BUILD_SBOM_SHA256";
+/// SBOM file name inside OUT_DIR
+#[doc(hidden)]
+pub const SBOM_FILE: &str = "ransomeye_sbom.cdx.json";
+
+/// Commit recorded when the build had neither a git checkout nor RANSOMEYE_BUILD_COMMIT
+pub const UNKNOWN_COMMIT: &str = "unknown";
+
+#[derive(Debug, Error)]
+pub enum BuildInfoError {
+    #[error("Release manifest {0} unreadable: {1}")]
+    ManifestUnreadable(String, String),
+    #[error("Release manifest {0} invalid: {1}")]
+    ManifestInvalid(String, String),
+    #[error("Build does not match release manifest {0}: {1}")]
+    Mismatch(String, String),
+    #[error("Cannot hash running executable: {0}")]
+    Executable(String),
+}
+
+/// Provenance embedded at build time; see [`build_info!`].
+#[derive(Debug, Clone, Copy)]
+pub struct BuildInfo {
+    pub package: &'static str,
+    pub version: &'static str,
+    pub git_commit: &'static str,
+    pub cargo_lock_sha256: &'static str,
+    pub sbom_sha256: &'static str,
+    /// CycloneDX 1.5 JSON
+    pub sbom: &'static str,
+}
+
+/// Expands to the [`BuildInfo`] of the calling crate. The crate's build.rs must call [`emit`].
+#[macro_export]
+macro_rules! build_info {
+    () => {
+        $crate::BuildInfo {
+            package: env!("CARGO_PKG_NAME"),
+            version: env!("CARGO_PKG_VERSION"),
+            git_commit: env!("RANSOMEYE_BUILD_GIT_COMMIT"),
+            cargo_lock_sha256: env!("RANSOMEYE_BUILD_CARGO_LOCK_SHA256"),
+            sbom_sha256: env!("RANSOMEYE_BUILD_SBOM_SHA256"),
+            sbom: include_str!(concat!(env!("OUT_DIR"), "/ransomeye_sbom.cdx.json")),
+        }
+    };
+}
+
+impl BuildInfo {
+    /// Compact provenance for components.build_metadata and service heartbeats (no SBOM body).
+    pub fn metadata(&self) -> JsonValue {
+        json!({
+            "package": self.package,
+            "version": self.version,
+            "git_commit": self.git_commit,
+            "cargo_lock_sha256": self.cargo_lock_sha256,
+            "sbom_sha256": self.sbom_sha256,
+        })
+    }
+
+    /// `--version --json` document: metadata plus the parsed SBOM.
+    pub fn to_json(&self, binary: &str) -> JsonValue {
+        let mut doc = self.metadata();
+        doc["binary"] = json!(binary);
+        doc["sbom"] = serde_json::from_str(self.sbom).unwrap_or(JsonValue::Null);
+        doc
+    }
+
+    pub fn version_line(&self, binary: &str) -> String {
+        format!(
+            "{binary} {} (package {}, commit {}, Cargo.lock sha256 {})",
+            self.version, self.package, self.git_commit, self.cargo_lock_sha256
+        )
+    }
+
+    /// Print `--version` / `-V` (plain, or JSON with `--json`) to stdout and exit 0; otherwise return.
+    pub fn handle_version_flag(&self, binary: &str) {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        if !args.iter().any(|a| a == "--version" || a == "-V") {
+            return;
+        }
+        let out = if args.iter().any(|a| a == "--json") {
+            serde_json::to_string_pretty(&self.to_json(binary)).unwrap_or_default()
+        } else {
+            self.version_line(binary)
+        };
+        // A closed pipe (`| head`) is not an error worth a panic
+        let _ = writeln!(std::io::stdout().lock(), "{out}");
+        std::process::exit(0);
+    }
+
+    /// Verify against RANSOMEYE_RELEASE_MANIFEST if set. Ok(None) when unset (not a release
+    /// deployment), Ok(Some(release)) when verified.
+    pub fn verify_release_from_env(&self, binary: &str) -> Result<Option<String>, BuildInfoError> {
+        let Some(path) = std::env::var(RELEASE_MANIFEST_ENV).ok().filter(|p| !p.trim().is_empty()) else {
+            return Ok(None);
+        };
+        let manifest = ReleaseManifest::load(Path::new(&path))?;
+        let exe = std::env::current_exe().map_err(|e| BuildInfoError::Executable(e.to_string()))?;
+        let exe_sha256 = sha256_file(&exe).map_err(|e| BuildInfoError::Executable(format!("{}: {e}", exe.display())))?;
+        manifest.verify(self, binary, &exe_sha256).map_err(|e| BuildInfoError::Mismatch(path, e))?;
+        Ok(Some(manifest.release))
+    }
+}
+
+pub(crate) fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(crypto::digest::Sha256::digest(data))
+}
+
+fn sha256_file(path: &Path) -> std::io::Result<String> {
+    use std::io::Read;
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = crypto::digest::Sha256::new();
+    let mut buf = vec![0u8; 1 << 16];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
@@ -0,0 +1,152 @@
+// Path and File Name : /home/ransomeye/rebuild/core/build_info/src/release.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Release manifest (commit, Cargo.lock hash, per-package SBOM hash and per-binary SHA-256) and the startup check of a running binary against it
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{BuildInfo, BuildInfoError, UNKNOWN_COMMIT};
+
+/// Path of the release manifest installed next to the binaries
+pub const RELEASE_MANIFEST_ENV: &str = "RANSOMEYE_RELEASE_MANIFEST";
+
+/// Written by the release pipeline from the `--version --json` output of every shipped binary.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReleaseManifest {
+    pub release: String,
+    pub git_commit: String,
+    pub cargo_lock_sha256: String,
+    pub components: Vec<ReleaseComponent>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReleaseComponent {
+    /// Cargo package (engine, ingest, agent-linux, ...)
+    pub package: String,
+    pub version: String,
+    pub sbom_sha256: String,
+    /// Binary name -> SHA-256 (hex) of the shipped executable
+    pub binaries: BTreeMap<String, String>,
+}
+
+impl ReleaseManifest {
+    pub fn load(path: &Path) -> Result<Self, BuildInfoError> {
+        let shown = path.display().to_string();
+        let raw = std::fs::read(path).map_err(|e| BuildInfoError::ManifestUnreadable(shown.clone(), e.to_string()))?;
+        let manifest: Self =
+            serde_json::from_slice(&raw).map_err(|e| BuildInfoError::ManifestInvalid(shown.clone(), e.to_string()))?;
+        manifest.validate().map_err(|e| BuildInfoError::ManifestInvalid(shown, e))?;
+        Ok(manifest)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.release.trim().is_empty() {
+            return Err("release is empty".to_string());
+        }
+        if self.git_commit == UNKNOWN_COMMIT {
+            return Err("git_commit is unknown; releases are built from a commit".to_string());
+        }
+        let mut packages = std::collections::BTreeSet::new();
+        for c in &self.components {
+            if !packages.insert(c.package.as_str()) {
+                return Err(format!("package {} listed twice", c.package));
+            }
+            if c.binaries.is_empty() {
+                return Err(format!("package {} lists no binaries", c.package));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the embedded build of `binary` (running executable hash `exe_sha256`).
+    pub fn verify(&self, info: &BuildInfo, binary: &str, exe_sha256: &str) -> Result<(), String> {
+        if info.git_commit != self.git_commit {
+            return Err(format!("commit {} != release commit {}", info.git_commit, self.git_commit));
+        }
+        if info.cargo_lock_sha256 != self.cargo_lock_sha256 {
+            return Err(format!(
+                "Cargo.lock sha256 {} != release {}",
+                info.cargo_lock_sha256, self.cargo_lock_sha256
+            ));
+        }
+        let component = self
+            .components
+            .iter()
+            .find(|c| c.package == info.package)
+            .ok_or_else(|| format!("package {} is not part of release {}", info.package, self.release))?;
+        if component.version != info.version {
+            return Err(format!("version {} != release version {}", info.version, component.version));
+        }
+        if component.sbom_sha256 != info.sbom_sha256 {
+            return Err(format!("SBOM sha256 {} != release {}", info.sbom_sha256, component.sbom_sha256));
+        }
+        let expected = component
+            .binaries
+            .get(binary)
+            .ok_or_else(|| format!("binary {binary} is not listed for package {}", info.package))?;
+        if !expected.eq_ignore_ascii_case(exe_sha256) {
+            return Err(format!("executable sha256 {exe_sha256} != release {expected}"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMMIT: &str = "0123456789abcdef0123456789abcdef01234567";
+
+    fn info() -> BuildInfo {
+        BuildInfo {
+            package: "ingest",
+            version: "1.0.0",
+            git_commit: COMMIT,
+            cargo_lock_sha256: "11",
+            sbom_sha256: "22",
+            sbom: "{}",
+        }
+    }
+
+    fn manifest() -> ReleaseManifest {
+        ReleaseManifest {
+            release: "2026.10.1".to_string(),
+            git_commit: COMMIT.to_string(),
+            cargo_lock_sha256: "11".to_string(),
+            components: vec![ReleaseComponent {
+                package: "ingest".to_string(),
+                version: "1.0.0".to_string(),
+                sbom_sha256: "22".to_string(),
+                binaries: BTreeMap::from([("ingest-http".to_string(), "ab".repeat(32))]),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_matching_build_verifies() {
+        let m = manifest();
+        assert!(m.validate().is_ok());
+        assert!(m.verify(&info(), "ingest-http", &"AB".repeat(32)).is_ok());
+    }
+
+    #[test]
+    fn test_any_drift_is_refused() {
+        let m = manifest();
+        let exe = "ab".repeat(32);
+        assert!(m.verify(&BuildInfo { git_commit: UNKNOWN_COMMIT, ..info() }, "ingest-http", &exe).unwrap_err().contains("commit"));
+        assert!(m.verify(&BuildInfo { cargo_lock_sha256: "99", ..info() }, "ingest-http", &exe).unwrap_err().contains("Cargo.lock"));
+        assert!(m.verify(&BuildInfo { sbom_sha256: "99", ..info() }, "ingest-http", &exe).unwrap_err().contains("SBOM"));
+        assert!(m.verify(&BuildInfo { version: "1.0.1", ..info() }, "ingest-http", &exe).unwrap_err().contains("version"));
+        assert!(m.verify(&BuildInfo { package: "engine", ..info() }, "ingest-http", &exe).unwrap_err().contains("not part"));
+        assert!(m.verify(&info(), "ingest", &exe).unwrap_err().contains("not listed"));
+        assert!(m.verify(&info(), "ingest-http", &"cd".repeat(32)).unwrap_err().contains("executable"));
+
+        let mut unknown = manifest();
+        unknown.git_commit = UNKNOWN_COMMIT.to_string();
+        assert!(unknown.validate().is_err());
+    }
+}
@@ -5,6 +5,8 @@
 use thiserror::Error;
 use tracing::{error, warn, debug};
 
+use crate::topics::{self, RoutedTopic};
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub enum ComponentRole {
     Agent,
@@ -16,6 +18,39 @@ pub enum ComponentRole {
     Dispatcher,
 }
 
+impl std::str::FromStr for ComponentRole {
+    type Err = AclError;
+
+    /// Parse the role carried in a bus certificate's OU (case-insensitive)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "agent" => Ok(ComponentRole::Agent),
+            "dpi" => Ok(ComponentRole::DPI),
+            "ui" => Ok(ComponentRole::UI),
+            "governor" => Ok(ComponentRole::Governor),
+            "core" => Ok(ComponentRole::Core),
+            "ingestion" => Ok(ComponentRole::Ingestion),
+            "dispatcher" => Ok(ComponentRole::Dispatcher),
+            other => Err(AclError::InvalidRole(other.to_string())),
+        }
+    }
+}
+
+impl ComponentRole {
+    /// Lowercase name (certificate OU, role segment of routed topics)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ComponentRole::Agent => "agent",
+            ComponentRole::DPI => "dpi",
+            ComponentRole::UI => "ui",
+            ComponentRole::Governor => "governor",
+            ComponentRole::Core => "core",
+            ComponentRole::Ingestion => "ingestion",
+            ComponentRole::Dispatcher => "dispatcher",
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub enum MessageType {
     TelemetryPublish,
@@ -33,6 +68,8 @@ pub enum AclError {
     InvalidRole(String),
     #[error("Invalid message type: {0}")]
     InvalidMessageType(String),
+    #[error("Invalid topic: {0}")]
+    InvalidTopic(#[from] topics::TopicError),
 }
 
 /// Access Control List for message bus
@@ -122,6 +159,9 @@ impl Acl {
     }
     
     /// Check if component can subscribe to topic
+    ///
+    /// `topic` may be a wildcard pattern; the namespace is always a literal prefix, so a
+    /// pattern never reaches past the namespaces the role may read.
     pub fn can_subscribe(role: &ComponentRole, topic: &str) -> bool {
         match role {
             ComponentRole::UI => topic.starts_with("query.") || topic.starts_with("telemetry."),
@@ -131,6 +171,46 @@ impl Acl {
             _ => false, // Agents and DPI cannot subscribe
         }
     }
+
+    /// Topic namespace each message type must be published under
+    pub fn topic_prefix(message_type: &MessageType) -> &'static str {
+        match message_type {
+            MessageType::TelemetryPublish => "telemetry.",
+            MessageType::CommandPublish => "command.",
+            MessageType::QueryOnly => "query.",
+            MessageType::AlertPublish => "alert.",
+            MessageType::HeartbeatPublish => "heartbeat.",
+        }
+    }
+
+    /// Check if component can publish message type on topic
+    ///
+    /// Binds the topic namespace to the message type so a role allowed to publish
+    /// telemetry cannot place it on a command topic. On a routed topic the role segment
+    /// must be the publisher's own role (Core relays for every role).
+    ///
+    /// FAIL-CLOSED: Returns error on access denial
+    pub fn can_publish_topic(role: &ComponentRole, message_type: &MessageType, topic: &str) -> Result<bool, AclError> {
+        Self::can_publish(role, message_type)?;
+        let prefix = Self::topic_prefix(message_type);
+        if !topic.starts_with(prefix) || topic.len() == prefix.len() {
+            warn!("ACL check failed: {:?} published {:?} on topic outside {}*: {}", role, message_type, prefix, topic);
+            return Err(AclError::AccessDenied(
+                format!("{:?} must be published under {}* (got {})", message_type, prefix, topic)
+            ));
+        }
+        topics::validate_topic(topic)?;
+        if topics::is_routed(topic) {
+            let routed = RoutedTopic::parse(topic)?;
+            if routed.key.role != *role && *role != ComponentRole::Core {
+                error!("SECURITY VIOLATION: {:?} published on routing key of role {:?}: {}", role, routed.key.role, topic);
+                return Err(AclError::AccessDenied(
+                    format!("{:?} cannot publish on behalf of {:?} (topic {})", role, routed.key.role, topic)
+                ));
+            }
+        }
+        Ok(true)
+    }
 }
 
 #[cfg(test)]
@@ -162,5 +242,21 @@ mod tests {
         let result = Acl::can_publish(&ComponentRole::Governor, &MessageType::TelemetryPublish);
         assert!(result.is_err());
     }
-}
+    
+    #[test]
+    fn test_topic_must_match_message_type() {
+        assert!(Acl::can_publish_topic(&ComponentRole::Agent, &MessageType::TelemetryPublish, "telemetry.linux").is_ok());
+        assert!(Acl::can_publish_topic(&ComponentRole::Agent, &MessageType::TelemetryPublish, "command.isolate").is_err());
+        assert!(Acl::can_publish_topic(&ComponentRole::Core, &MessageType::AlertPublish, "alert.").is_err());
+    }
 
+    #[test]
+    fn test_routed_topic_role_must_match_publisher() {
+        let topic = "alert.acme.dc1.agent.ransomware";
+        assert!(Acl::can_publish_topic(&ComponentRole::Agent, &MessageType::AlertPublish, topic).is_ok());
+        assert!(Acl::can_publish_topic(&ComponentRole::DPI, &MessageType::AlertPublish, topic).is_err());
+        assert!(Acl::can_publish_topic(&ComponentRole::Core, &MessageType::AlertPublish, topic).is_ok());
+        assert!(Acl::can_publish_topic(&ComponentRole::Agent, &MessageType::AlertPublish, "alert.acme.dc1.robot.x").is_err());
+        assert!(Acl::can_publish_topic(&ComponentRole::Agent, &MessageType::AlertPublish, "alert.acme.*.agent.x").is_err());
+    }
+}
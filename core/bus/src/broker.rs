@@ -0,0 +1,631 @@
+// Path and File Name : /home/ransomeye/rebuild/core/bus/src/broker.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: mTLS pub/sub broker - certificate-bound component identity, role-based topic ACLs, durable subscriptions with resume
+
+/*
+ * Broker
+ *
+ * Identity comes from the verified client certificate only: CN = component_id,
+ * OU = ComponentRole. The component_id / component_role inside a published BusMessage
+ * must match the certificate or the message is rejected as an ACL violation.
+ *
+ * Durable subscriptions are persisted in the DurableStore before the publisher gets
+ * ACCEPTED, so undelivered messages survive subscriber disconnects and broker restarts.
+ * Each attached durable subscriber has a delivery pump that streams pending entries in
+ * sequence order with backpressure. Non-durable subscriptions are best-effort: a
+ * subscriber whose outbound queue is full misses the message (logged).
+ *
+ * Each durable queue has its own lock; the broker-wide map is locked only to look slots
+ * up or create one, never across queue I/O. Appends and acks (write + fsync) run on the
+ * blocking pool. A message routed to several durable queues is appended to all of them
+ * or to none (durable_store::append_all), so a publisher that retries after REJECTED
+ * does not leave duplicates in the queues that had succeeded.
+ */
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use chrono::Utc;
+use parking_lot::Mutex;
+use rustls::ServerConfig;
+use thiserror::Error;
+use tokio::io::BufReader;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, Notify};
+use tokio::task::JoinHandle;
+use tokio_rustls::TlsAcceptor;
+use tracing::{debug, error, info, warn};
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+use crate::acl::{Acl, ComponentRole};
+use crate::client::BusMessage;
+use crate::durable_store::{self, DurableMeta, DurableQueue, DurableStore, DurableStoreError};
+use crate::mtls::{load_server_cert, MtlsError};
+use crate::protocol::{read_frame, topic_matches, write_frame, BrokerFrame, ClientFrame, ProtocolError};
+use crate::topics::validate_pattern;
+
+/// Outbound frames buffered per connection.
+const OUTBOUND_QUEUE_DEPTH: usize = 1024;
+/// Entries a durable pump copies out of the store per lock acquisition.
+const PUMP_BATCH: usize = 256;
+
+#[derive(Debug, Error)]
+pub enum BrokerError {
+    #[error("Broker configuration invalid: {0}")]
+    Config(String),
+    #[error("mTLS configuration failed: {0}")]
+    Mtls(#[from] MtlsError),
+    #[error("Durable store failed: {0}")]
+    Store(#[from] DurableStoreError),
+    #[error("Client identity rejected: {0}")]
+    Identity(String),
+    #[error("Protocol error: {0}")]
+    Protocol(#[from] ProtocolError),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Debug, Clone)]
+pub struct BrokerConfig {
+    pub listen_addr: String,
+    pub server_cert_path: String,
+    pub server_key_path: String,
+    pub root_ca_path: String,
+    pub store_dir: PathBuf,
+    pub max_durable_backlog: usize,
+    pub durable_fsync: bool,
+}
+
+impl BrokerConfig {
+    /// FAIL-CLOSED: certificate paths are required; there is no plaintext mode.
+    pub fn from_env() -> Result<Self, BrokerError> {
+        let required = |key: &str| {
+            std::env::var(key).map_err(|_| BrokerError::Config(format!("{} environment variable not set", key)))
+        };
+        let max_durable_backlog = match std::env::var("RANSOMEYE_BUS_DURABLE_MAX_BACKLOG") {
+            Ok(v) => v.parse::<usize>()
+                .ok()
+                .filter(|n| *n > 0)
+                .ok_or_else(|| BrokerError::Config(format!("Invalid RANSOMEYE_BUS_DURABLE_MAX_BACKLOG='{}'", v)))?,
+            Err(_) => 100_000,
+        };
+        let durable_fsync = match std::env::var("RANSOMEYE_BUS_DURABLE_FSYNC") {
+            Ok(v) => v.parse::<bool>()
+                .map_err(|_| BrokerError::Config(format!("Invalid RANSOMEYE_BUS_DURABLE_FSYNC='{}' (expected true/false)", v)))?,
+            Err(_) => true,
+        };
+        Ok(Self {
+            listen_addr: std::env::var("RANSOMEYE_BUS_LISTEN_ADDR").unwrap_or_else(|_| "0.0.0.0:8443".to_string()),
+            server_cert_path: required("RANSOMEYE_BUS_SERVER_CERT")?,
+            server_key_path: required("RANSOMEYE_BUS_SERVER_KEY")?,
+            root_ca_path: required("RANSOMEYE_BUS_ROOT_CA_PATH")?,
+            store_dir: PathBuf::from(
+                std::env::var("RANSOMEYE_BUS_STORE_DIR").unwrap_or_else(|_| "/var/lib/ransomeye/bus".to_string()),
+            ),
+            max_durable_backlog,
+            durable_fsync,
+        })
+    }
+}
+
+/// Component identity proven by the client certificate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeerIdentity {
+    pub component_id: String,
+    pub role: ComponentRole,
+}
+
+/// Extract CN (component_id) and OU (role) from a DER client certificate.
+pub fn peer_identity(cert_der: &[u8]) -> Result<PeerIdentity, BrokerError> {
+    let (_, cert) = X509Certificate::from_der(cert_der)
+        .map_err(|e| BrokerError::Identity(format!("Failed to parse client certificate: {}", e)))?;
+    let subject = cert.subject();
+    let component_id = subject.iter_common_name()
+        .next()
+        .and_then(|attr| attr.as_str().ok())
+        .filter(|cn| !cn.is_empty())
+        .ok_or_else(|| BrokerError::Identity("Client certificate has no CN".to_string()))?
+        .to_string();
+    let ou = subject.iter_organizational_unit()
+        .next()
+        .and_then(|attr| attr.as_str().ok())
+        .ok_or_else(|| BrokerError::Identity(format!("Client certificate for {} has no OU (component role)", component_id)))?;
+    let role = ou.parse::<ComponentRole>()
+        .map_err(|e| BrokerError::Identity(format!("Client certificate for {}: {}", component_id, e)))?;
+    Ok(PeerIdentity { component_id, role })
+}
+
+struct Attached {
+    conn_id: u64,
+    wake: Arc<Notify>,
+}
+
+struct DurableSlot {
+    /// Copy of the queue's metadata (never changes), for routing without locking the queue
+    meta: DurableMeta,
+    state: tokio::sync::Mutex<DurableState>,
+}
+
+struct DurableState {
+    queue: DurableQueue,
+    attached: Option<Attached>,
+}
+
+impl DurableSlot {
+    fn new(queue: DurableQueue) -> Arc<Self> {
+        Arc::new(Self { meta: queue.meta().clone(), state: tokio::sync::Mutex::new(DurableState { queue, attached: None }) })
+    }
+}
+
+struct LiveSubscription {
+    conn_id: u64,
+    pattern: String,
+    tx: mpsc::Sender<BrokerFrame>,
+}
+
+pub struct Broker {
+    tls: Arc<ServerConfig>,
+    store: DurableStore,
+    durables: Mutex<HashMap<String, Arc<DurableSlot>>>,
+    live: Mutex<Vec<LiveSubscription>>,
+    next_conn_id: AtomicU64,
+}
+
+impl Broker {
+    pub fn new(config: &BrokerConfig) -> Result<Self, BrokerError> {
+        let tls = load_server_cert(&config.server_cert_path, &config.server_key_path, &config.root_ca_path)?;
+        Self::with_tls(Arc::new(tls), config)
+    }
+
+    pub fn with_tls(tls: Arc<ServerConfig>, config: &BrokerConfig) -> Result<Self, BrokerError> {
+        let store = DurableStore::new(&config.store_dir, config.max_durable_backlog, config.durable_fsync)?;
+        let durables = store.load_all()?
+            .into_iter()
+            .map(|queue| (queue.meta().durable_name.clone(), DurableSlot::new(queue)))
+            .collect::<HashMap<_, _>>();
+        info!("Bus broker store ready at {} ({} durable subscriptions)", config.store_dir.display(), durables.len());
+        Ok(Self {
+            tls,
+            store,
+            durables: Mutex::new(durables),
+            live: Mutex::new(Vec::new()),
+            next_conn_id: AtomicU64::new(1),
+        })
+    }
+
+    /// Accept connections forever; per-connection failures never stop the listener.
+    pub async fn serve(self: Arc<Self>, listener: TcpListener) -> Result<(), BrokerError> {
+        info!("Bus broker listening on {}", listener.local_addr()?);
+        loop {
+            let (tcp, peer_addr) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    // e.g. EMFILE - back off instead of spinning
+                    error!("Bus accept failed: {}", e);
+                    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                    continue;
+                }
+            };
+            let broker = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = broker.handle_connection(tcp, peer_addr).await {
+                    warn!("Bus connection from {} closed: {}", peer_addr, e);
+                }
+            });
+        }
+    }
+
+    async fn handle_connection(self: Arc<Self>, tcp: TcpStream, peer_addr: SocketAddr) -> Result<(), BrokerError> {
+        let tls = TlsAcceptor::from(self.tls.clone()).accept(tcp).await?;
+        let identity = {
+            let (_, conn) = tls.get_ref();
+            let cert = conn.peer_certificates()
+                .and_then(|certs| certs.first())
+                .ok_or_else(|| BrokerError::Identity("No client certificate presented".to_string()))?;
+            peer_identity(&cert.0)?
+        };
+        let conn_id = self.next_conn_id.fetch_add(1, Ordering::Relaxed);
+        info!("Bus client connected: {} ({:?}) from {} [conn {}]", identity.component_id, identity.role, peer_addr, conn_id);
+
+        let (read_half, mut write_half) = tokio::io::split(tls);
+        let mut reader = BufReader::new(read_half);
+        let (tx, mut rx) = mpsc::channel::<BrokerFrame>(OUTBOUND_QUEUE_DEPTH);
+        let writer = tokio::spawn(async move {
+            while let Some(frame) = rx.recv().await {
+                if let Err(e) = write_frame(&mut write_half, &frame).await {
+                    debug!("Bus writer stopped: {}", e);
+                    break;
+                }
+            }
+        });
+
+        let mut pumps: Vec<JoinHandle<()>> = Vec::new();
+        let result = loop {
+            let frame = match read_frame::<_, ClientFrame>(&mut reader).await {
+                Ok(frame) => frame,
+                Err(ProtocolError::Closed) => break Ok(()),
+                Err(e) => {
+                    let _ = tx.send(BrokerFrame::Rejected { reason: e.to_string() }).await;
+                    break Err(e.into());
+                }
+            };
+            match frame {
+                ClientFrame::Publish { message } => {
+                    let reply = self.publish(&identity, message).await;
+                    if tx.send(reply).await.is_err() {
+                        break Ok(());
+                    }
+                }
+                ClientFrame::Subscribe { topic, durable_name, resume_after } => {
+                    let (reply, pump) = self.subscribe(conn_id, &identity, &tx, topic, durable_name, resume_after).await;
+                    if tx.send(reply).await.is_err() {
+                        break Ok(());
+                    }
+                    pumps.extend(pump);
+                }
+                ClientFrame::Ack { durable_name, seq } => self.ack(&identity, &durable_name, seq).await,
+            }
+        };
+
+        self.detach(conn_id).await;
+        for pump in pumps {
+            pump.abort();
+        }
+        drop(tx);
+        let _ = writer.await;
+        info!("Bus client disconnected: {} [conn {}]", identity.component_id, conn_id);
+        result
+    }
+
+    async fn publish(&self, identity: &PeerIdentity, message: BusMessage) -> BrokerFrame {
+        if message.component_id != identity.component_id || message.component_role != identity.role {
+            error!(
+                "SECURITY VIOLATION: {} ({:?}) published as {} ({:?})",
+                identity.component_id, identity.role, message.component_id, message.component_role
+            );
+            return BrokerFrame::AclViolation {
+                reason: "Message identity does not match client certificate".to_string(),
+            };
+        }
+        if let Err(e) = Acl::can_publish_topic(&identity.role, &message.message_type, &message.topic) {
+            return BrokerFrame::AclViolation { reason: e.to_string() };
+        }
+
+        // Persist for durable subscribers before acknowledging the publisher.
+        let mut targets: Vec<Arc<DurableSlot>> = self
+            .durables
+            .lock()
+            .values()
+            .filter(|slot| {
+                topic_matches(&slot.meta.topic_pattern, &message.topic)
+                    && Acl::can_subscribe(&slot.meta.owner_role, &message.topic)
+            })
+            .cloned()
+            .collect();
+        if !targets.is_empty() {
+            // Queues are locked in name order, so concurrent publishers cannot deadlock
+            targets.sort_by(|a, b| a.meta.durable_name.cmp(&b.meta.durable_name));
+            let entry = message.clone();
+            let persisted = tokio::task::spawn_blocking(move || append_durable(&targets, &entry))
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|res| res.map_err(|e| e.to_string()));
+            if let Err(e) = persisted {
+                error!("Durable append of {} on {} failed: {}", message.message_id, message.topic, e);
+                return BrokerFrame::Rejected { reason: "Durable persistence failed".to_string() };
+            }
+        }
+
+        let live = self.live.lock();
+        for sub in live.iter().filter(|s| topic_matches(&s.pattern, &message.topic)) {
+            let frame = BrokerFrame::Deliver {
+                topic: message.topic.clone(),
+                durable_name: None,
+                seq: 0,
+                message: message.clone(),
+            };
+            if let Err(mpsc::error::TrySendError::Full(_)) = sub.tx.try_send(frame) {
+                warn!("Dropped {} for slow non-durable subscriber [conn {}]", message.message_id, sub.conn_id);
+            }
+        }
+        debug!("Published {} on {}", message.message_id, message.topic);
+        BrokerFrame::Accepted { message_id: message.message_id }
+    }
+
+    async fn subscribe(
+        self: &Arc<Self>,
+        conn_id: u64,
+        identity: &PeerIdentity,
+        tx: &mpsc::Sender<BrokerFrame>,
+        topic: String,
+        durable_name: Option<String>,
+        resume_after: Option<u64>,
+    ) -> (BrokerFrame, Option<JoinHandle<()>>) {
+        if let Err(e) = validate_pattern(&topic) {
+            return (BrokerFrame::Rejected { reason: e.to_string() }, None);
+        }
+        if !Acl::can_subscribe(&identity.role, &topic) {
+            warn!("ACL check failed: {:?} cannot subscribe to {}", identity.role, topic);
+            return (
+                BrokerFrame::AclViolation { reason: format!("{:?} cannot subscribe to {}", identity.role, topic) },
+                None,
+            );
+        }
+
+        let Some(name) = durable_name else {
+            self.live.lock().push(LiveSubscription { conn_id, pattern: topic.clone(), tx: tx.clone() });
+            info!("{} subscribed to {} (non-durable)", identity.component_id, topic);
+            return (BrokerFrame::Subscribed { topic, durable_name: None, backlog: 0 }, None);
+        };
+
+        let slot = {
+            let mut durables = self.durables.lock();
+            match durables.get(&name) {
+                Some(slot) => slot.clone(),
+                None => {
+                    let meta = DurableMeta {
+                        durable_name: name.clone(),
+                        topic_pattern: topic.clone(),
+                        owner_component_id: identity.component_id.clone(),
+                        owner_role: identity.role.clone(),
+                        created_at: Utc::now(),
+                    };
+                    match self.store.create(meta) {
+                        Ok(queue) => durables.entry(name.clone()).or_insert(DurableSlot::new(queue)).clone(),
+                        Err(e) => return (BrokerFrame::Rejected { reason: e.to_string() }, None),
+                    }
+                }
+            }
+        };
+        let meta = &slot.meta;
+        if meta.owner_component_id != identity.component_id || meta.topic_pattern != topic {
+            warn!(
+                "{} attempted to attach durable subscription {} owned by {} on {}",
+                identity.component_id, name, meta.owner_component_id, meta.topic_pattern
+            );
+            return (
+                BrokerFrame::AclViolation { reason: DurableStoreError::OwnershipMismatch(name).to_string() },
+                None,
+            );
+        }
+
+        let wake = Arc::new(Notify::new());
+        let attach = {
+            let (slot, wake) = (slot.clone(), wake.clone());
+            tokio::task::spawn_blocking(move || {
+                let mut state = slot.state.blocking_lock();
+                if let Some(seq) = resume_after {
+                    state.queue.ack(seq)?;
+                }
+                if let Some(previous) = state.attached.replace(Attached { conn_id, wake }) {
+                    info!("Durable subscription {} taken over by conn {} (was conn {})", slot.meta.durable_name, conn_id, previous.conn_id);
+                }
+                Ok::<_, DurableStoreError>(state.queue.backlog_len())
+            })
+        };
+        let backlog = match attach.await {
+            Ok(Ok(backlog)) => backlog,
+            Ok(Err(e)) => return (BrokerFrame::Rejected { reason: e.to_string() }, None),
+            Err(e) => return (BrokerFrame::Rejected { reason: format!("Durable subscription {} unavailable: {}", name, e) }, None),
+        };
+
+        info!(
+            "{} attached durable subscription {} on {} (resume_after={:?}, backlog={})",
+            identity.component_id, name, topic, resume_after, backlog
+        );
+        let pump = tokio::spawn(Self::pump(slot, conn_id, tx.clone(), wake));
+        (BrokerFrame::Subscribed { topic, durable_name: Some(name), backlog }, Some(pump))
+    }
+
+    /// Stream pending entries of one durable subscription to its attached connection.
+    async fn pump(slot: Arc<DurableSlot>, conn_id: u64, tx: mpsc::Sender<BrokerFrame>, wake: Arc<Notify>) {
+        let mut cursor = 0u64;
+        loop {
+            let batch: Vec<(u64, BusMessage)> = {
+                let state = slot.state.lock().await;
+                if state.attached.as_ref().map(|a| a.conn_id) != Some(conn_id) {
+                    // Detached or taken over by a newer connection
+                    return;
+                }
+                state.queue.pending_after(cursor).take(PUMP_BATCH).cloned().collect()
+            };
+            if batch.is_empty() {
+                wake.notified().await;
+                continue;
+            }
+            for (seq, message) in batch {
+                let frame = BrokerFrame::Deliver {
+                    topic: message.topic.clone(),
+                    durable_name: Some(slot.meta.durable_name.clone()),
+                    seq,
+                    message,
+                };
+                if tx.send(frame).await.is_err() {
+                    return;
+                }
+                cursor = seq;
+            }
+        }
+    }
+
+    async fn ack(&self, identity: &PeerIdentity, durable_name: &str, seq: u64) {
+        let Some(slot) = self.durables.lock().get(durable_name).cloned() else {
+            warn!("{} acknowledged unknown durable subscription {}", identity.component_id, durable_name);
+            return;
+        };
+        if slot.meta.owner_component_id != identity.component_id {
+            warn!("{} acknowledged durable subscription {} it does not own", identity.component_id, durable_name);
+            return;
+        }
+        let acked = tokio::task::spawn_blocking(move || slot.state.blocking_lock().queue.ack(seq))
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|res| res.map_err(|e| e.to_string()));
+        if let Err(e) = acked {
+            error!("Failed to persist ack {} for {}: {}", seq, durable_name, e);
+        }
+    }
+
+    async fn detach(&self, conn_id: u64) {
+        let slots: Vec<Arc<DurableSlot>> = self.durables.lock().values().cloned().collect();
+        for slot in slots {
+            let mut state = slot.state.lock().await;
+            if state.attached.as_ref().map(|a| a.conn_id) == Some(conn_id) {
+                state.attached = None;
+            }
+        }
+        self.live.lock().retain(|s| s.conn_id != conn_id);
+    }
+}
+
+/// Append a message to every target queue, all or nothing, and wake their pumps. Blocking
+/// (file writes and fsync): runs on the blocking pool. `targets` must be in name order.
+fn append_durable(targets: &[Arc<DurableSlot>], message: &BusMessage) -> Result<(), DurableStoreError> {
+    let mut states: Vec<_> = targets.iter().map(|slot| slot.state.blocking_lock()).collect();
+    let mut queues: Vec<&mut DurableQueue> = states.iter_mut().map(|state| &mut state.queue).collect();
+    durable_store::append_all(&mut queues, message)?;
+    for attached in states.iter().filter_map(|state| state.attached.as_ref()) {
+        attached.wake.notify_one();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::acl::MessageType;
+    use rustls::server::ResolvesServerCertUsingSni;
+    use std::time::Duration;
+
+    /// Broker over a scratch store; the handlers are driven directly, so no certificates are needed.
+    fn broker(store_dir: &std::path::Path) -> Arc<Broker> {
+        let tls = ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_cert_resolver(Arc::new(ResolvesServerCertUsingSni::new()));
+        let config = BrokerConfig {
+            listen_addr: "127.0.0.1:0".to_string(),
+            server_cert_path: String::new(),
+            server_key_path: String::new(),
+            root_ca_path: String::new(),
+            store_dir: store_dir.to_path_buf(),
+            max_durable_backlog: 1000,
+            durable_fsync: false,
+        };
+        Arc::new(Broker::with_tls(Arc::new(tls), &config).unwrap())
+    }
+
+    fn identity(component_id: &str, role: ComponentRole) -> PeerIdentity {
+        PeerIdentity { component_id: component_id.to_string(), role }
+    }
+
+    fn alert(id: &str, from: &PeerIdentity) -> BusMessage {
+        BusMessage {
+            message_id: id.to_string(),
+            component_id: from.component_id.clone(),
+            component_role: from.role.clone(),
+            message_type: MessageType::AlertPublish,
+            topic: "alert.high".to_string(),
+            data: id.as_bytes().to_vec(),
+            signature: String::new(),
+            timestamp: Utc::now(),
+        }
+    }
+
+    async fn next_delivery(rx: &mut mpsc::Receiver<BrokerFrame>) -> (u64, String) {
+        match tokio::time::timeout(Duration::from_secs(5), rx.recv()).await {
+            Ok(Some(BrokerFrame::Deliver { seq, message, .. })) => (seq, message.message_id),
+            other => panic!("expected a delivery, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rejects_message_identity_mismatch() {
+        let root = std::env::temp_dir().join(format!("ransomeye-bus-broker-{}", uuid::Uuid::new_v4()));
+        let broker = broker(&root);
+        let core = identity("orchestrator", ComponentRole::Core);
+        let (tx, _rx) = mpsc::channel(OUTBOUND_QUEUE_DEPTH);
+        let (reply, _pump) = broker.subscribe(1, &core, &tx, "alert.*".to_string(), Some("orch".to_string()), None).await;
+        assert!(matches!(reply, BrokerFrame::Subscribed { backlog: 0, .. }));
+
+        let agent = identity("agent-1", ComponentRole::Agent);
+        let spoofed_id = alert("m1", &identity("agent-2", ComponentRole::Agent));
+        assert!(matches!(broker.publish(&agent, spoofed_id).await, BrokerFrame::AclViolation { .. }));
+        let spoofed_role = alert("m2", &identity("agent-1", ComponentRole::Core));
+        assert!(matches!(broker.publish(&agent, spoofed_role).await, BrokerFrame::AclViolation { .. }));
+
+        // Rejected messages never reach a durable queue
+        let slot = broker.durables.lock().get("orch").cloned().unwrap();
+        assert_eq!(slot.state.lock().await.queue.backlog_len(), 0);
+        assert!(matches!(broker.publish(&agent, alert("m3", &agent)).await, BrokerFrame::Accepted { .. }));
+        assert_eq!(slot.state.lock().await.queue.backlog_len(), 1);
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_acl_denied() {
+        let root = std::env::temp_dir().join(format!("ransomeye-bus-broker-{}", uuid::Uuid::new_v4()));
+        let broker = broker(&root);
+        let (tx, _rx) = mpsc::channel(OUTBOUND_QUEUE_DEPTH);
+
+        let agent = identity("agent-1", ComponentRole::Agent);
+        let (reply, pump) = broker.subscribe(1, &agent, &tx, "alert.*".to_string(), None, None).await;
+        assert!(matches!(reply, BrokerFrame::AclViolation { .. }) && pump.is_none());
+        let ui = identity("console", ComponentRole::UI);
+        let (reply, _) = broker.subscribe(2, &ui, &tx, "command.*".to_string(), Some("ui-commands".to_string()), None).await;
+        assert!(matches!(reply, BrokerFrame::AclViolation { .. }));
+        assert!(broker.durables.lock().is_empty() && broker.live.lock().is_empty());
+
+        // A durable subscription cannot be attached by another component
+        let dispatcher = identity("dispatcher-1", ComponentRole::Dispatcher);
+        let (reply, _) = broker.subscribe(3, &dispatcher, &tx, "alert.*".to_string(), Some("alerts".to_string()), None).await;
+        assert!(matches!(reply, BrokerFrame::Subscribed { .. }));
+        let governor = identity("governor-1", ComponentRole::Governor);
+        let (reply, pump) = broker.subscribe(4, &governor, &tx, "alert.*".to_string(), Some("alerts".to_string()), None).await;
+        assert!(matches!(reply, BrokerFrame::AclViolation { .. }) && pump.is_none());
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[tokio::test]
+    async fn test_durable_resume_after_reconnect() {
+        let root = std::env::temp_dir().join(format!("ransomeye-bus-broker-{}", uuid::Uuid::new_v4()));
+        let broker = broker(&root);
+        let core = identity("orchestrator", ComponentRole::Core);
+        let agent = identity("agent-1", ComponentRole::Agent);
+        let name = || Some("orch-alerts".to_string());
+
+        let (tx, mut rx) = mpsc::channel(OUTBOUND_QUEUE_DEPTH);
+        let (_, pump) = broker.subscribe(1, &core, &tx, "alert.*".to_string(), name(), None).await;
+        for id in ["m1", "m2", "m3"] {
+            assert!(matches!(broker.publish(&agent, alert(id, &agent)).await, BrokerFrame::Accepted { .. }));
+        }
+        assert_eq!(next_delivery(&mut rx).await, (1, "m1".to_string()));
+        assert_eq!(next_delivery(&mut rx).await, (2, "m2".to_string()));
+        broker.ack(&core, "orch-alerts", 1).await;
+
+        // Disconnect, miss two more messages, reconnect having processed m2
+        broker.detach(1).await;
+        pump.unwrap().abort();
+        for id in ["m4", "m5"] {
+            assert!(matches!(broker.publish(&agent, alert(id, &agent)).await, BrokerFrame::Accepted { .. }));
+        }
+        let (tx, mut rx) = mpsc::channel(OUTBOUND_QUEUE_DEPTH);
+        let (reply, _pump) = broker.subscribe(2, &core, &tx, "alert.*".to_string(), name(), Some(2)).await;
+        assert!(matches!(reply, BrokerFrame::Subscribed { backlog: 3, .. }));
+        for expected in [(3, "m3"), (4, "m4"), (5, "m5")] {
+            assert_eq!(next_delivery(&mut rx).await, (expected.0, expected.1.to_string()));
+        }
+
+        // The resume cursor is persisted: a restarted broker holds the same backlog
+        broker.detach(2).await;
+        drop(broker);
+        let restarted = self::broker(&root);
+        let (tx, _rx) = mpsc::channel(OUTBOUND_QUEUE_DEPTH);
+        let (reply, _) = restarted.subscribe(3, &core, &tx, "alert.*".to_string(), name(), None).await;
+        assert!(matches!(reply, BrokerFrame::Subscribed { backlog: 3, .. }));
+        std::fs::remove_dir_all(&root).ok();
+    }
+}
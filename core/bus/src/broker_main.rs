@@ -0,0 +1,53 @@
+// Path and File Name : /home/ransomeye/rebuild/core/bus/src/broker_main.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Event bus broker binary - mTLS pub/sub server with role-based topic ACLs and durable subscriptions
+
+use std::process;
+use std::sync::Arc;
+
+use tokio::net::TcpListener;
+use tracing::{error, info};
+
+use bus::{Broker, BrokerConfig};
+
+#[tokio::main]
+async fn main() {
+    tracing_subscriber::fmt::init();
+
+    let config = match BrokerConfig::from_env() {
+        Ok(c) => c,
+        Err(e) => {
+            error!("FAIL-CLOSED: {}", e);
+            process::exit(1);
+        }
+    };
+
+    let broker = match Broker::new(&config) {
+        Ok(b) => Arc::new(b),
+        Err(e) => {
+            error!("FAIL-CLOSED: Bus broker initialization failed: {}", e);
+            process::exit(1);
+        }
+    };
+
+    let listener = match TcpListener::bind(&config.listen_addr).await {
+        Ok(l) => l,
+        Err(e) => {
+            error!("Failed to bind bus broker on {}: {}", config.listen_addr, e);
+            process::exit(1);
+        }
+    };
+
+    tokio::select! {
+        result = broker.serve(listener) => {
+            if let Err(e) = result {
+                error!("Bus broker stopped: {}", e);
+                process::exit(1);
+            }
+        }
+        _ = tokio::signal::ctrl_c() => {
+            // Durable entries are already on disk; nothing to flush.
+            info!("Bus broker shutting down");
+        }
+    }
+}
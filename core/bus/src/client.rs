@@ -3,9 +3,11 @@
 // Details of functionality of this file: Message bus client with mTLS and ACL enforcement
 
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::TcpStream;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio_rustls::{TlsConnector, TlsStream};
+use tokio::io::{BufReader, ReadHalf, WriteHalf};
+use tokio_rustls::TlsConnector;
+use tokio_rustls::client::TlsStream;
 use rustls::ClientConfig;
 use tracing::{error, warn, debug, info};
 use thiserror::Error;
@@ -15,6 +17,8 @@ use uuid::Uuid;
 use crate::mtls::{load_client_cert, MtlsError};
 use crate::acl::{Acl, ComponentRole, MessageType, AclError};
 use crate::integrity::MessageIntegrity;
+use crate::protocol::{read_frame, write_frame, BrokerFrame, ClientFrame};
+use crate::topics::{validate_pattern, TopicBuilder};
 
 #[derive(Debug, Error)]
 pub enum BusClientError {
@@ -89,6 +93,12 @@ impl BusClient {
         })
     }
     
+    /// Routed topic builder with this client's role filled in
+    /// (add tenant, site and subject, then `build`)
+    pub fn topic(&self, message_type: MessageType) -> TopicBuilder {
+        TopicBuilder::new(message_type).role(self.component_role.clone())
+    }
+    
     /// Publish message to bus
     /// 
     /// FAIL-CLOSED: Returns error on ACL violation or connection failure
@@ -99,7 +109,7 @@ impl BusClient {
         data: Vec<u8>,
     ) -> Result<(), BusClientError> {
         // Step 1: Check ACL
-        Acl::can_publish(&self.component_role, &message_type)?;
+        Acl::can_publish_topic(&self.component_role, &message_type, topic)?;
         
         // Step 2: Create message
         let message_id = Uuid::new_v4().to_string();
@@ -127,45 +137,201 @@ impl BusClient {
     }
     
     async fn send_message(&self, message: &BusMessage) -> Result<(), BusClientError> {
-        // Serialize message
-        let message_json = serde_json::to_vec(message)
-            .map_err(|e| BusClientError::SendFailed(format!("Serialization failed: {}", e)))?;
-        
-        // Connect to server
-        let tcp_stream = TcpStream::connect(&self.server_addr).await
-            .map_err(|e| BusClientError::ConnectionFailed(format!("TCP connect failed: {}", e)))?;
-        
-        // Establish mTLS connection
-        let connector = TlsConnector::from(self.tls_config.clone());
-        let host = self.server_addr.split(':').next().unwrap_or("localhost");
-        let server_name = rustls::ServerName::try_from(host)
-            .map_err(|_| BusClientError::ConnectionFailed(format!("Invalid server name: {}", host)))?;
-        let mut tls_stream = connector.connect(server_name, tcp_stream).await
-            .map_err(|e| BusClientError::ConnectionFailed(format!("TLS handshake failed: {}", e)))?;
-        
-        // Send message
-        tls_stream.write_all(&message_json).await
+        let (mut reader, mut writer) = connect(&self.tls_config, &self.server_addr).await?;
+        
+        write_frame(&mut writer, &ClientFrame::Publish { message: message.clone() }).await
             .map_err(|e| BusClientError::SendFailed(format!("Write failed: {}", e)))?;
         
-        // Read response
-        let mut response = vec![0u8; 1024];
-        let n = tls_stream.read(&mut response).await
+        let response: BrokerFrame = read_frame(&mut reader).await
             .map_err(|e| BusClientError::ReceiveFailed(format!("Read failed: {}", e)))?;
         
-        let response_str = String::from_utf8_lossy(&response[..n]);
-        if response_str.contains("ACL_VIOLATION") {
+        match response {
+            BrokerFrame::Accepted { .. } => {
+                debug!("Message published successfully: {}", message.message_id);
+                Ok(())
+            }
+            BrokerFrame::AclViolation { reason } => Err(BusClientError::AclViolation(AclError::AccessDenied(
+                format!("Server rejected message due to ACL violation: {}", reason)
+            ))),
+            BrokerFrame::Rejected { reason } => {
+                Err(BusClientError::SendFailed(format!("Server rejected message: {}", reason)))
+            }
+            other => Err(BusClientError::ReceiveFailed(format!("Unexpected broker response: {:?}", other))),
+        }
+    }
+    
+    /// Subscribe to a topic or pattern (`*` segments, trailing `*`; see topics::SubscriptionFilter)
+    /// 
+    /// With `durable_name`, the broker retains messages while this subscriber is
+    /// disconnected and the returned subscription resumes from its last ack after
+    /// reconnecting. Delivery is at-least-once.
+    pub async fn subscribe(
+        &self,
+        topic: &str,
+        durable_name: Option<&str>,
+    ) -> Result<BusSubscription, BusClientError> {
+        validate_pattern(topic).map_err(AclError::from)?;
+        if !Acl::can_subscribe(&self.component_role, topic) {
             return Err(BusClientError::AclViolation(AclError::AccessDenied(
-                "Server rejected message due to ACL violation".to_string()
+                format!("{:?} cannot subscribe to {}", self.component_role, topic)
             )));
         }
         
-        if response_str.contains("REJECTED") {
-            return Err(BusClientError::SendFailed("Server rejected message".to_string()));
+        let mut subscription = BusSubscription {
+            tls_config: self.tls_config.clone(),
+            server_addr: self.server_addr.clone(),
+            topic: topic.to_string(),
+            durable_name: durable_name.map(|s| s.to_string()),
+            last_acked: None,
+            last_delivered: 0,
+            stream: None,
+        };
+        subscription.establish().await?;
+        Ok(subscription)
+    }
+}
+
+type BusReader = BufReader<ReadHalf<TlsStream<TcpStream>>>;
+type BusWriter = WriteHalf<TlsStream<TcpStream>>;
+
+async fn connect(tls_config: &Arc<ClientConfig>, server_addr: &str) -> Result<(BusReader, BusWriter), BusClientError> {
+    let tcp_stream = TcpStream::connect(server_addr).await
+        .map_err(|e| BusClientError::ConnectionFailed(format!("TCP connect failed: {}", e)))?;
+    
+    let connector = TlsConnector::from(tls_config.clone());
+    let host = server_addr.split(':').next().unwrap_or("localhost");
+    let server_name = rustls::ServerName::try_from(host)
+        .map_err(|_| BusClientError::ConnectionFailed(format!("Invalid server name: {}", host)))?;
+    let tls_stream = connector.connect(server_name, tcp_stream).await
+        .map_err(|e| BusClientError::ConnectionFailed(format!("TLS handshake failed: {}", e)))?;
+    
+    let (read_half, write_half) = tokio::io::split(tls_stream);
+    Ok((BufReader::new(read_half), write_half))
+}
+
+/// Message delivered to a subscriber. `seq` is 0 for non-durable subscriptions.
+#[derive(Debug, Clone)]
+pub struct BusDelivery {
+    pub seq: u64,
+    pub message: BusMessage,
+}
+
+/// Live subscription with automatic reconnect
+/// 
+/// Connection loss is retried with exponential backoff (capped at RECONNECT_MAX_BACKOFF).
+/// Durable subscriptions resend their last ack as `resume_after` so the broker redelivers
+/// everything not yet acknowledged; ACL rejections are returned to the caller.
+pub struct BusSubscription {
+    tls_config: Arc<ClientConfig>,
+    server_addr: String,
+    topic: String,
+    durable_name: Option<String>,
+    last_acked: Option<u64>,
+    /// Highest durable sequence handed to the caller on the current connection.
+    last_delivered: u64,
+    stream: Option<(BusReader, BusWriter)>,
+}
+
+const RECONNECT_INITIAL_BACKOFF: Duration = Duration::from_millis(250);
+const RECONNECT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+impl BusSubscription {
+    /// Wait for the next message, reconnecting as needed
+    pub async fn next(&mut self) -> Result<BusDelivery, BusClientError> {
+        loop {
+            if self.stream.is_none() {
+                self.reconnect().await?;
+            }
+            let Some((reader, _)) = self.stream.as_mut() else { continue };
+            
+            match read_frame::<_, BrokerFrame>(reader).await {
+                Ok(BrokerFrame::Deliver { seq, message, .. }) => {
+                    // A takeover race can replay entries already handed out on this connection.
+                    if seq != 0 && seq <= self.last_delivered {
+                        continue;
+                    }
+                    self.last_delivered = seq;
+                    return Ok(BusDelivery { seq, message });
+                }
+                Ok(BrokerFrame::AclViolation { reason }) => {
+                    self.stream = None;
+                    return Err(BusClientError::AclViolation(AclError::AccessDenied(reason)));
+                }
+                Ok(other) => {
+                    warn!("Ignoring unexpected broker frame on subscription {}: {:?}", self.topic, other);
+                }
+                Err(e) => {
+                    warn!("Bus subscription {} lost connection: {}", self.topic, e);
+                    self.stream = None;
+                }
+            }
+        }
+    }
+    
+    /// Acknowledge a durable delivery (and everything before it)
+    /// 
+    /// If the connection is down the ack is kept locally and sent as `resume_after`
+    /// on reconnect. No-op for non-durable subscriptions.
+    pub async fn ack(&mut self, seq: u64) -> Result<(), BusClientError> {
+        let Some(durable_name) = self.durable_name.clone() else {
+            return Ok(());
+        };
+        if self.last_acked.map_or(false, |acked| seq <= acked) {
+            return Ok(());
+        }
+        self.last_acked = Some(seq);
+        if let Some((_, writer)) = self.stream.as_mut() {
+            if let Err(e) = write_frame(writer, &ClientFrame::Ack { durable_name, seq }).await {
+                warn!("Bus ack for {} deferred until reconnect: {}", self.topic, e);
+                self.stream = None;
+            }
         }
-        
-        debug!("Message published successfully: {}", message.message_id);
         Ok(())
     }
+    
+    async fn reconnect(&mut self) -> Result<(), BusClientError> {
+        let mut backoff = RECONNECT_INITIAL_BACKOFF;
+        loop {
+            match self.establish().await {
+                Ok(()) => return Ok(()),
+                Err(e @ BusClientError::AclViolation(_)) => return Err(e),
+                Err(e) => {
+                    warn!("Bus subscription {} reconnect failed ({}); retrying in {:?}", self.topic, e, backoff);
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(RECONNECT_MAX_BACKOFF);
+                }
+            }
+        }
+    }
+    
+    async fn establish(&mut self) -> Result<(), BusClientError> {
+        let (mut reader, mut writer) = connect(&self.tls_config, &self.server_addr).await?;
+        let subscribe = ClientFrame::Subscribe {
+            topic: self.topic.clone(),
+            durable_name: self.durable_name.clone(),
+            resume_after: self.last_acked,
+        };
+        write_frame(&mut writer, &subscribe).await
+            .map_err(|e| BusClientError::SendFailed(format!("Subscribe failed: {}", e)))?;
+        
+        match read_frame::<_, BrokerFrame>(&mut reader).await
+            .map_err(|e| BusClientError::ReceiveFailed(format!("Subscribe response failed: {}", e)))?
+        {
+            BrokerFrame::Subscribed { backlog, .. } => {
+                info!(
+                    "Subscribed to {} (durable: {:?}, resume_after: {:?}, backlog: {})",
+                    self.topic, self.durable_name, self.last_acked, backlog
+                );
+                // Redelivery restarts after the last ack on a fresh connection.
+                self.last_delivered = self.last_acked.unwrap_or(0);
+                self.stream = Some((reader, writer));
+                Ok(())
+            }
+            BrokerFrame::AclViolation { reason } => Err(BusClientError::AclViolation(AclError::AccessDenied(reason))),
+            BrokerFrame::Rejected { reason } => Err(BusClientError::ConnectionFailed(format!("Subscribe rejected: {}", reason))),
+            other => Err(BusClientError::ReceiveFailed(format!("Unexpected subscribe response: {:?}", other))),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,469 @@
+// Path and File Name : /home/ransomeye/rebuild/core/bus/src/durable_store.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: File-backed queues of undelivered messages for durable bus subscriptions, surviving subscriber disconnects and broker restarts
+
+/*
+ * Durable Store Layout
+ *
+ *   <store_dir>/<durable_name>/meta.json   subscription owner, role and topic pattern
+ *   <store_dir>/<durable_name>/queue.log   append-only {"seq","message"} lines
+ *   <store_dir>/<durable_name>/ack         highest acknowledged sequence (atomic rename)
+ *
+ * On load, entries at or below the ack cursor are skipped and a torn trailing line (crash
+ * mid-append) is ignored. The log is rewritten with only pending entries once it grows past
+ * COMPACT_THRESHOLD_BYTES. When a queue exceeds its backlog limit the oldest entries are
+ * dropped and the ack cursor advanced past them (logged, never silent).
+ *
+ * A message routed to several queues is appended with append_all: every log is written
+ * first, and only when all writes succeed does the message become pending anywhere. A
+ * failed write truncates the logs already written, so a publisher that retries after a
+ * rejection never leaves a duplicate behind.
+ */
+
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{debug, error, info, warn};
+
+use crate::acl::ComponentRole;
+use crate::client::BusMessage;
+
+const COMPACT_THRESHOLD_BYTES: u64 = 8 * 1024 * 1024;
+
+#[derive(Debug, Error)]
+pub enum DurableStoreError {
+    #[error("Invalid durable subscription name: {0}")]
+    InvalidName(String),
+    #[error("Durable subscription {0} belongs to another component or topic")]
+    OwnershipMismatch(String),
+    #[error("Durable store I/O failed: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Durable store corrupted: {0}")]
+    Corrupted(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DurableMeta {
+    pub durable_name: String,
+    pub topic_pattern: String,
+    pub owner_component_id: String,
+    pub owner_role: ComponentRole,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LogEntry {
+    seq: u64,
+    message: BusMessage,
+}
+
+/// A log entry written but not yet pending (see append_all).
+struct PreparedEntry {
+    /// Log length before the entry, restored on rollback
+    len_before: u64,
+    line_len: u64,
+}
+
+/// One durable subscription's queue. Callers serialize access (the broker holds it behind a mutex).
+pub struct DurableQueue {
+    meta: DurableMeta,
+    dir: PathBuf,
+    log: File,
+    log_bytes: u64,
+    pending: VecDeque<(u64, BusMessage)>,
+    acked: u64,
+    next_seq: u64,
+    max_backlog: usize,
+    fsync: bool,
+}
+
+impl DurableQueue {
+    pub fn meta(&self) -> &DurableMeta {
+        &self.meta
+    }
+
+    pub fn acked(&self) -> u64 {
+        self.acked
+    }
+
+    pub fn backlog_len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Pending messages with sequence greater than `after`, in order.
+    pub fn pending_after(&self, after: u64) -> impl Iterator<Item = &(u64, BusMessage)> {
+        self.pending.iter().filter(move |(seq, _)| *seq > after)
+    }
+
+    /// Persist a message and return its sequence number.
+    pub fn append(&mut self, message: &BusMessage) -> Result<u64, DurableStoreError> {
+        let prepared = self.prepare(message)?;
+        Ok(self.commit(message, prepared))
+    }
+
+    /// Write the next entry to the log. A failed write is truncated away before returning.
+    fn prepare(&mut self, message: &BusMessage) -> Result<PreparedEntry, DurableStoreError> {
+        let mut line = serde_json::to_vec(&LogEntry { seq: self.next_seq, message: message.clone() })
+            .map_err(|e| DurableStoreError::Corrupted(format!("serialize entry: {}", e)))?;
+        line.push(b'\n');
+        let len_before = self.log.metadata()?.len();
+        let written = self.log.write_all(&line).and_then(|_| if self.fsync { self.log.sync_data() } else { Ok(()) });
+        if let Err(e) = written {
+            self.rollback(len_before);
+            return Err(e.into());
+        }
+        Ok(PreparedEntry { len_before, line_len: line.len() as u64 })
+    }
+
+    /// Drop a prepared (or torn) entry from the log.
+    fn rollback(&mut self, len_before: u64) {
+        let truncated = self.log.set_len(len_before).and_then(|_| if self.fsync { self.log.sync_data() } else { Ok(()) });
+        if let Err(e) = truncated {
+            error!(
+                "Failed to roll back durable queue {} to {} bytes: {} - the entry may be redelivered after a restart",
+                self.meta.durable_name, len_before, e
+            );
+        }
+    }
+
+    /// Make a prepared entry pending and return its sequence number.
+    fn commit(&mut self, message: &BusMessage, prepared: PreparedEntry) -> u64 {
+        let seq = self.next_seq;
+        self.log_bytes += prepared.line_len;
+        self.next_seq += 1;
+        self.pending.push_back((seq, message.clone()));
+
+        if self.pending.len() > self.max_backlog {
+            let overflow = self.pending.len() - self.max_backlog;
+            let mut last_dropped = self.acked;
+            for _ in 0..overflow {
+                if let Some((dropped, _)) = self.pending.pop_front() {
+                    last_dropped = dropped;
+                }
+            }
+            warn!(
+                "Durable subscription {} backlog exceeded {} messages; dropped {} oldest (through seq {})",
+                self.meta.durable_name, self.max_backlog, overflow, last_dropped
+            );
+            // The message itself is persisted; the dropped entries only reappear after a restart
+            if let Err(e) = self.write_ack(last_dropped) {
+                error!("Failed to persist backlog cursor {} for {}: {}", last_dropped, self.meta.durable_name, e);
+            }
+        }
+        seq
+    }
+
+    /// Acknowledge everything up to and including `seq`.
+    pub fn ack(&mut self, seq: u64) -> Result<(), DurableStoreError> {
+        if seq <= self.acked {
+            return Ok(());
+        }
+        // Never acknowledge beyond what was actually issued.
+        let seq = seq.min(self.next_seq.saturating_sub(1));
+        while matches!(self.pending.front(), Some((s, _)) if *s <= seq) {
+            self.pending.pop_front();
+        }
+        self.write_ack(seq)?;
+        if self.log_bytes > COMPACT_THRESHOLD_BYTES {
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    fn write_ack(&mut self, seq: u64) -> Result<(), DurableStoreError> {
+        let tmp = self.dir.join("ack.tmp");
+        {
+            let mut f = File::create(&tmp)?;
+            f.write_all(seq.to_string().as_bytes())?;
+            if self.fsync {
+                f.sync_all()?;
+            }
+        }
+        fs::rename(&tmp, self.dir.join("ack"))?;
+        self.acked = seq;
+        Ok(())
+    }
+
+    fn compact(&mut self) -> Result<(), DurableStoreError> {
+        let tmp = self.dir.join("queue.log.tmp");
+        let mut bytes = 0u64;
+        {
+            let mut f = File::create(&tmp)?;
+            for (seq, message) in &self.pending {
+                let mut line = serde_json::to_vec(&LogEntry { seq: *seq, message: message.clone() })
+                    .map_err(|e| DurableStoreError::Corrupted(format!("serialize entry: {}", e)))?;
+                line.push(b'\n');
+                f.write_all(&line)?;
+                bytes += line.len() as u64;
+            }
+            f.sync_all()?;
+        }
+        fs::rename(&tmp, self.dir.join("queue.log"))?;
+        self.log = OpenOptions::new().append(true).open(self.dir.join("queue.log"))?;
+        debug!("Compacted durable queue {} ({} -> {} bytes)", self.meta.durable_name, self.log_bytes, bytes);
+        self.log_bytes = bytes;
+        Ok(())
+    }
+}
+
+/// Append one message to several queues, all or nothing. Returns the sequence number in each
+/// queue, in order. If any write fails, the entries already written are rolled back and the
+/// message is pending in none of them.
+pub fn append_all(queues: &mut [&mut DurableQueue], message: &BusMessage) -> Result<Vec<u64>, DurableStoreError> {
+    let mut prepared = Vec::with_capacity(queues.len());
+    let written = queues.iter_mut().try_for_each(|queue| {
+        prepared.push(queue.prepare(message)?);
+        Ok(())
+    });
+    if let Err(e) = written {
+        for (queue, entry) in queues.iter_mut().zip(&prepared) {
+            queue.rollback(entry.len_before);
+        }
+        return Err(e);
+    }
+    Ok(queues.iter_mut().zip(prepared).map(|(queue, entry)| queue.commit(message, entry)).collect())
+}
+
+pub struct DurableStore {
+    root: PathBuf,
+    max_backlog: usize,
+    fsync: bool,
+}
+
+impl DurableStore {
+    pub fn new(root: &Path, max_backlog: usize, fsync: bool) -> Result<Self, DurableStoreError> {
+        fs::create_dir_all(root)?;
+        Ok(Self { root: root.to_path_buf(), max_backlog, fsync })
+    }
+
+    /// Load every durable queue persisted under the store root (broker startup).
+    pub fn load_all(&self) -> Result<Vec<DurableQueue>, DurableStoreError> {
+        let mut queues = Vec::new();
+        for entry in fs::read_dir(&self.root)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() || !entry.path().join("meta.json").exists() {
+                continue;
+            }
+            let queue = self.open_dir(&entry.path())?;
+            info!(
+                "Loaded durable subscription {} ({} pending, acked through {})",
+                queue.meta.durable_name, queue.pending.len(), queue.acked
+            );
+            queues.push(queue);
+        }
+        Ok(queues)
+    }
+
+    /// Create a new durable queue. Fails if the name is not path-safe.
+    pub fn create(&self, meta: DurableMeta) -> Result<DurableQueue, DurableStoreError> {
+        validate_durable_name(&meta.durable_name)?;
+        let dir = self.root.join(&meta.durable_name);
+        fs::create_dir_all(&dir)?;
+        let meta_json = serde_json::to_vec_pretty(&meta)
+            .map_err(|e| DurableStoreError::Corrupted(format!("serialize meta: {}", e)))?;
+        let tmp = dir.join("meta.json.tmp");
+        fs::write(&tmp, meta_json)?;
+        fs::rename(&tmp, dir.join("meta.json"))?;
+        self.open_dir(&dir)
+    }
+
+    fn open_dir(&self, dir: &Path) -> Result<DurableQueue, DurableStoreError> {
+        let meta: DurableMeta = serde_json::from_slice(&fs::read(dir.join("meta.json"))?)
+            .map_err(|e| DurableStoreError::Corrupted(format!("{}: {}", dir.display(), e)))?;
+        let acked = match fs::read_to_string(dir.join("ack")) {
+            Ok(s) => s.trim().parse::<u64>()
+                .map_err(|e| DurableStoreError::Corrupted(format!("{}/ack: {}", dir.display(), e)))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(e.into()),
+        };
+
+        let log_path = dir.join("queue.log");
+        let mut pending = VecDeque::new();
+        let mut next_seq = acked + 1;
+        let mut log_bytes = 0u64;
+        if log_path.exists() {
+            let reader = BufReader::new(File::open(&log_path)?);
+            for line in reader.split(b'\n') {
+                let line = line?;
+                log_bytes += line.len() as u64 + 1;
+                match serde_json::from_slice::<LogEntry>(&line) {
+                    Ok(entry) => {
+                        next_seq = next_seq.max(entry.seq + 1);
+                        if entry.seq > acked {
+                            pending.push_back((entry.seq, entry.message));
+                        }
+                    }
+                    Err(e) => {
+                        // Torn append from a crash; the entry was never acknowledged to the publisher.
+                        warn!("Skipping unreadable entry in {}: {}", log_path.display(), e);
+                    }
+                }
+            }
+        }
+
+        let mut log = OpenOptions::new().create(true).append(true).open(&log_path)?;
+        // Terminate a torn trailing line so the next entry starts cleanly.
+        if log_bytes > 0 && !ends_with_newline(&log_path)? {
+            log.write_all(b"\n")?;
+        }
+
+        Ok(DurableQueue {
+            meta,
+            dir: dir.to_path_buf(),
+            log,
+            log_bytes,
+            pending,
+            acked,
+            next_seq,
+            max_backlog: self.max_backlog,
+            fsync: self.fsync,
+        })
+    }
+}
+
+fn ends_with_newline(path: &Path) -> Result<bool, DurableStoreError> {
+    use std::io::{Read, Seek, SeekFrom};
+    let mut f = File::open(path)?;
+    if f.metadata()?.len() == 0 {
+        return Ok(true);
+    }
+    f.seek(SeekFrom::End(-1))?;
+    let mut last = [0u8; 1];
+    f.read_exact(&mut last)?;
+    Ok(last[0] == b'\n')
+}
+
+/// Durable names become directory names: 1-64 chars of [A-Za-z0-9_.-], no leading dot.
+pub fn validate_durable_name(name: &str) -> Result<(), DurableStoreError> {
+    let valid = !name.is_empty()
+        && name.len() <= 64
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.');
+    if valid {
+        Ok(())
+    } else {
+        Err(DurableStoreError::InvalidName(name.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::acl::MessageType;
+
+    fn message(id: &str) -> BusMessage {
+        BusMessage {
+            message_id: id.to_string(),
+            component_id: "agent-1".to_string(),
+            component_role: ComponentRole::Agent,
+            message_type: MessageType::AlertPublish,
+            topic: "alert.high".to_string(),
+            data: id.as_bytes().to_vec(),
+            signature: String::new(),
+            timestamp: Utc::now(),
+        }
+    }
+
+    fn meta(name: &str) -> DurableMeta {
+        DurableMeta {
+            durable_name: name.to_string(),
+            topic_pattern: "alert.*".to_string(),
+            owner_component_id: "orchestrator".to_string(),
+            owner_role: ComponentRole::Core,
+            created_at: Utc::now(),
+        }
+    }
+
+    fn temp_root() -> PathBuf {
+        std::env::temp_dir().join(format!("ransomeye-bus-store-{}", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn test_rejects_path_traversal_names() {
+        assert!(validate_durable_name("orchestrator-alerts").is_ok());
+        assert!(validate_durable_name("../etc").is_err());
+        assert!(validate_durable_name("a/b").is_err());
+        assert!(validate_durable_name("").is_err());
+    }
+
+    #[test]
+    fn test_pending_survives_reload_and_ack() {
+        let root = temp_root();
+        let store = DurableStore::new(&root, 1000, false).unwrap();
+        let mut q = store.create(meta("orch")).unwrap();
+        for i in 0..5 {
+            assert_eq!(q.append(&message(&format!("m{}", i))).unwrap(), i + 1);
+        }
+        q.ack(2).unwrap();
+        drop(q);
+
+        let mut loaded = store.load_all().unwrap();
+        assert_eq!(loaded.len(), 1);
+        let q = loaded.pop().unwrap();
+        assert_eq!(q.acked(), 2);
+        let seqs: Vec<u64> = q.pending_after(0).map(|(s, _)| *s).collect();
+        assert_eq!(seqs, vec![3, 4, 5]);
+        let seqs: Vec<u64> = q.pending_after(4).map(|(s, _)| *s).collect();
+        assert_eq!(seqs, vec![5]);
+        fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_torn_tail_and_backlog_limit() {
+        let root = temp_root();
+        let store = DurableStore::new(&root, 3, false).unwrap();
+        let mut q = store.create(meta("orch")).unwrap();
+        q.append(&message("m1")).unwrap();
+        drop(q);
+        // Simulate crash mid-append
+        let mut f = OpenOptions::new().append(true).open(root.join("orch/queue.log")).unwrap();
+        f.write_all(b"{\"seq\":2,\"mess").unwrap();
+        drop(f);
+
+        let mut q = store.load_all().unwrap().pop().unwrap();
+        assert_eq!(q.backlog_len(), 1);
+        for i in 2..=5 {
+            q.append(&message(&format!("m{}", i))).unwrap();
+        }
+        // Limit 3: m1 and m2 dropped, cursor advanced past them
+        assert_eq!(q.backlog_len(), 3);
+        assert_eq!(q.acked(), 2);
+        drop(q);
+
+        let q = store.load_all().unwrap().pop().unwrap();
+        let seqs: Vec<u64> = q.pending_after(0).map(|(s, _)| *s).collect();
+        assert_eq!(seqs, vec![3, 4, 5]);
+        fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn test_append_all_is_all_or_nothing() {
+        let root = temp_root();
+        let store = DurableStore::new(&root, 1000, false).unwrap();
+        let mut a = store.create(meta("a")).unwrap();
+        let mut b = store.create(meta("b")).unwrap();
+        assert_eq!(append_all(&mut [&mut a, &mut b], &message("m1")).unwrap(), vec![1, 1]);
+
+        // b's log can no longer be written: a's entry must not survive either
+        b.log = File::open(root.join("b/queue.log")).unwrap();
+        let a_len = fs::metadata(root.join("a/queue.log")).unwrap().len();
+        assert!(append_all(&mut [&mut a, &mut b], &message("m2")).is_err());
+        assert_eq!((a.backlog_len(), b.backlog_len()), (1, 1));
+        assert_eq!(fs::metadata(root.join("a/queue.log")).unwrap().len(), a_len);
+
+        // The failed message took no sequence number
+        assert_eq!(a.append(&message("m3")).unwrap(), 2);
+        drop((a, b));
+        let mut loaded = store.load_all().unwrap();
+        loaded.sort_by(|x, y| x.meta().durable_name.cmp(&y.meta().durable_name));
+        let ids = |q: &DurableQueue| q.pending_after(0).map(|(_, m)| m.message_id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&loaded[0]), vec!["m1", "m3"]);
+        assert_eq!(ids(&loaded[1]), vec!["m1"]);
+        fs::remove_dir_all(&root).ok();
+    }
+}
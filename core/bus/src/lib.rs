@@ -6,8 +6,14 @@ pub mod mtls;
 pub mod acl;
 pub mod integrity;
 pub mod client;
+pub mod protocol;
+pub mod durable_store;
+pub mod broker;
+pub mod topics;
 
 pub use mtls::{load_client_cert, load_server_cert, MtlsError};
 pub use acl::{Acl, ComponentRole, MessageType, AclError};
 pub use integrity::{MessageIntegrity, IntegrityError};
-pub use client::{BusClient, BusMessage, BusClientError};
+pub use client::{BusClient, BusMessage, BusClientError, BusSubscription, BusDelivery};
+pub use broker::{Broker, BrokerConfig, BrokerError};
+pub use topics::{RoutedTopic, RoutingKey, SubscriptionFilter, TopicBuilder, TopicError};
@@ -0,0 +1,164 @@
+// Path and File Name : /home/ransomeye/rebuild/core/bus/src/protocol.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Bus wire protocol - newline-delimited JSON frames exchanged between BusClient and the broker over mTLS
+
+/*
+ * Wire Protocol
+ *
+ * One JSON object per line in each direction. serde_json never emits a raw newline, so
+ * the delimiter is unambiguous. Frames larger than MAX_FRAME_BYTES are rejected
+ * (fail-closed) rather than buffered.
+ *
+ * Durable subscriptions carry a per-subscription sequence number. Delivery is
+ * at-least-once: the broker redelivers everything after the last acknowledged
+ * sequence when a subscriber reconnects with `resume_after`.
+ */
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::client::BusMessage;
+
+/// Upper bound for a single frame (message payload plus envelope).
+pub const MAX_FRAME_BYTES: usize = 1024 * 1024;
+
+#[derive(Debug, Error)]
+pub enum ProtocolError {
+    #[error("Connection closed")]
+    Closed,
+    #[error("Frame exceeds maximum frame size (1 MiB)")]
+    FrameTooLarge,
+    #[error("Malformed frame: {0}")]
+    Malformed(String),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Client -> broker
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ClientFrame {
+    Publish { message: BusMessage },
+    /// `topic` is an exact topic or a pattern (see `topic_matches`), e.g. `alert.acme.*.agent.*`.
+    /// `durable_name` makes the broker retain undelivered messages while the subscriber is away.
+    Subscribe {
+        topic: String,
+        durable_name: Option<String>,
+        /// Last sequence the subscriber has fully processed (acknowledges everything up to it).
+        resume_after: Option<u64>,
+    },
+    Ack { durable_name: String, seq: u64 },
+}
+
+/// Broker -> client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum BrokerFrame {
+    Accepted { message_id: String },
+    Rejected { reason: String },
+    AclViolation { reason: String },
+    Subscribed { topic: String, durable_name: Option<String>, backlog: usize },
+    /// `seq` is 0 for non-durable subscriptions.
+    Deliver { topic: String, durable_name: Option<String>, seq: u64, message: BusMessage },
+}
+
+/// Subscription pattern match, segment by segment: a `*` segment matches exactly one segment;
+/// a trailing `*` matches the rest of the topic (prefix match).
+pub fn topic_matches(pattern: &str, topic: &str) -> bool {
+    let mut topic_rest = Some(topic);
+    let mut segments = pattern.split('.').peekable();
+    while let Some(segment) = segments.next() {
+        let Some(rest) = topic_rest else { return false };
+        if segments.peek().is_none() {
+            return match segment.strip_suffix('*') {
+                Some(prefix) => rest.starts_with(prefix) && rest.len() > prefix.len(),
+                None => rest == segment,
+            };
+        }
+        let (head, tail) = match rest.split_once('.') {
+            Some((head, tail)) => (head, Some(tail)),
+            None => (rest, None),
+        };
+        if segment != "*" && segment != head {
+            return false;
+        }
+        topic_rest = tail;
+    }
+    false
+}
+
+pub async fn write_frame<W, F>(writer: &mut W, frame: &F) -> Result<(), ProtocolError>
+where
+    W: AsyncWrite + Unpin,
+    F: Serialize,
+{
+    let mut buf = serde_json::to_vec(frame).map_err(|e| ProtocolError::Malformed(e.to_string()))?;
+    if buf.len() > MAX_FRAME_BYTES {
+        return Err(ProtocolError::FrameTooLarge);
+    }
+    buf.push(b'\n');
+    writer.write_all(&buf).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+pub async fn read_frame<R, F>(reader: &mut R) -> Result<F, ProtocolError>
+where
+    R: AsyncBufRead + Unpin,
+    F: for<'de> Deserialize<'de>,
+{
+    let mut buf = Vec::new();
+    let n = (&mut *reader).take(MAX_FRAME_BYTES as u64 + 1).read_until(b'\n', &mut buf).await?;
+    if n == 0 {
+        return Err(ProtocolError::Closed);
+    }
+    if buf.last() != Some(&b'\n') {
+        if buf.len() > MAX_FRAME_BYTES {
+            return Err(ProtocolError::FrameTooLarge);
+        }
+        // EOF mid-frame
+        return Err(ProtocolError::Closed);
+    }
+    buf.pop();
+    serde_json::from_slice(&buf).map_err(|e| ProtocolError::Malformed(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::BufReader;
+
+    #[test]
+    fn test_topic_matching() {
+        assert!(topic_matches("alert.*", "alert.high"));
+        assert!(topic_matches("alert.high", "alert.high"));
+        assert!(!topic_matches("alert.high", "alert.high.extra"));
+        assert!(!topic_matches("alert.*", "command.isolate"));
+        assert!(topic_matches("*", "telemetry.linux"));
+        assert!(topic_matches("alert.*.dc1.*.*", "alert.acme.dc1.agent.ransomware"));
+        assert!(!topic_matches("alert.*.dc1.*.*", "alert.acme.dc2.agent.ransomware"));
+        assert!(!topic_matches("alert.*.dc1.*.*", "alert.acme.dc1"));
+        assert!(!topic_matches("alert.*.x", "alert.x"));
+    }
+
+    #[tokio::test]
+    async fn test_frame_roundtrip_and_size_limit() {
+        let frame = ClientFrame::Ack { durable_name: "orchestrator-alerts".to_string(), seq: 7 };
+        let mut wire = Vec::new();
+        write_frame(&mut wire, &frame).await.unwrap();
+        let mut reader = BufReader::new(wire.as_slice());
+        match read_frame::<_, ClientFrame>(&mut reader).await.unwrap() {
+            ClientFrame::Ack { durable_name, seq } => {
+                assert_eq!(durable_name, "orchestrator-alerts");
+                assert_eq!(seq, 7);
+            }
+            other => panic!("unexpected frame: {:?}", other),
+        }
+        assert!(matches!(read_frame::<_, ClientFrame>(&mut reader).await, Err(ProtocolError::Closed)));
+
+        let oversized = vec![b'a'; MAX_FRAME_BYTES + 10];
+        let mut reader = BufReader::new(oversized.as_slice());
+        assert!(matches!(read_frame::<_, ClientFrame>(&mut reader).await, Err(ProtocolError::FrameTooLarge)));
+    }
+}
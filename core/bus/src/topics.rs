@@ -0,0 +1,350 @@
+// Path and File Name : /home/ransomeye/rebuild/core/bus/src/topics.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Failure-domain aware topic naming - routing keys (tenant, site, component role, subject), topic and subscription filter builders, topic/pattern validation
+
+/*
+ * Topic Naming
+ *
+ * Routed topics carry their failure domain in fixed positions:
+ *
+ *   <namespace>.<tenant>.<site>.<role>.<subject>[.<subject>...]
+ *   alert.acme.dc1.agent.ransomware.encryption
+ *
+ * namespace is bound to the message type (Acl::topic_prefix), role is the publisher's
+ * ComponentRole and must match its certificate (enforced by Acl::can_publish_topic on both
+ * client and broker; Core may relay for any role). Topics with fewer segments are flat
+ * topics (telemetry.accepted) and are routed by exact name only.
+ *
+ * Subscription patterns use `*` as a whole segment to match exactly one segment; a trailing
+ * `*` matches the rest of the topic (one or more segments, or the rest of a segment when it
+ * follows a prefix such as `alert.hi*`). Consumers scope by failure domain with
+ * SubscriptionFilter instead of filtering deliveries themselves:
+ *
+ *   alert.acme.*.agent.*    every alert from agents of tenant acme, any site
+ */
+
+use std::fmt;
+
+use thiserror::Error;
+
+use crate::acl::{Acl, ComponentRole, MessageType};
+
+/// Segments of a routed topic: namespace, tenant, site, role, subject (one or more)
+pub const ROUTED_MIN_SEGMENTS: usize = 5;
+pub const MAX_TOPIC_LEN: usize = 255;
+pub const MAX_SEGMENT_LEN: usize = 64;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum TopicError {
+    #[error("Invalid topic {0}: {1}")]
+    InvalidTopic(String, String),
+    #[error("Invalid subscription pattern {0}: {1}")]
+    InvalidPattern(String, String),
+    #[error("Routed topic is missing its {0}")]
+    Missing(&'static str),
+}
+
+fn check_segment(segment: &str) -> Result<(), String> {
+    if segment.is_empty() {
+        return Err("empty segment".to_string());
+    }
+    if segment.len() > MAX_SEGMENT_LEN {
+        return Err(format!("segment longer than {} bytes", MAX_SEGMENT_LEN));
+    }
+    if !segment.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-') {
+        return Err(format!("segment '{}' may only contain [A-Za-z0-9_-]", segment));
+    }
+    Ok(())
+}
+
+/// Topic a message may be published on: non-empty [A-Za-z0-9_-] segments, no wildcards.
+pub fn validate_topic(topic: &str) -> Result<(), TopicError> {
+    if topic.len() > MAX_TOPIC_LEN {
+        return Err(TopicError::InvalidTopic(topic.to_string(), format!("longer than {} bytes", MAX_TOPIC_LEN)));
+    }
+    for segment in topic.split('.') {
+        check_segment(segment).map_err(|reason| TopicError::InvalidTopic(topic.to_string(), reason))?;
+    }
+    Ok(())
+}
+
+/// Subscription pattern: topic segments, `*` segments, and at most one trailing `prefix*`.
+pub fn validate_pattern(pattern: &str) -> Result<(), TopicError> {
+    let invalid = |reason: String| TopicError::InvalidPattern(pattern.to_string(), reason);
+    if pattern.len() > MAX_TOPIC_LEN {
+        return Err(invalid(format!("longer than {} bytes", MAX_TOPIC_LEN)));
+    }
+    let segments: Vec<&str> = pattern.split('.').collect();
+    for (i, segment) in segments.iter().enumerate() {
+        if *segment == "*" {
+            continue;
+        }
+        let literal = match segment.strip_suffix('*') {
+            Some(prefix) if i + 1 == segments.len() && !prefix.is_empty() => prefix,
+            Some(_) => return Err(invalid("`*` must be a whole segment or end the pattern".to_string())),
+            None => segment,
+        };
+        check_segment(literal).map_err(invalid)?;
+    }
+    Ok(())
+}
+
+/// Whether `topic` is a routed topic (has tenant, site, role and subject positions).
+pub fn is_routed(topic: &str) -> bool {
+    topic.split('.').count() >= ROUTED_MIN_SEGMENTS
+}
+
+/// Failure domain and subject of a routed topic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoutingKey {
+    pub tenant: String,
+    pub site: String,
+    pub role: ComponentRole,
+    /// Dotted subject (one or more segments)
+    pub subject: String,
+}
+
+/// A parsed routed topic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoutedTopic {
+    pub message_type: MessageType,
+    pub key: RoutingKey,
+}
+
+const NAMESPACES: [MessageType; 5] = [
+    MessageType::TelemetryPublish,
+    MessageType::CommandPublish,
+    MessageType::QueryOnly,
+    MessageType::AlertPublish,
+    MessageType::HeartbeatPublish,
+];
+
+impl RoutedTopic {
+    /// Parse a routed topic; flat topics and unknown namespaces or roles are errors.
+    pub fn parse(topic: &str) -> Result<Self, TopicError> {
+        validate_topic(topic)?;
+        let invalid = |reason: &str| TopicError::InvalidTopic(topic.to_string(), reason.to_string());
+        let mut segments = topic.splitn(ROUTED_MIN_SEGMENTS, '.');
+        let (Some(namespace), Some(tenant), Some(site), Some(role), Some(subject)) =
+            (segments.next(), segments.next(), segments.next(), segments.next(), segments.next())
+        else {
+            return Err(invalid("not a routed topic (<namespace>.<tenant>.<site>.<role>.<subject>)"));
+        };
+        let message_type = NAMESPACES
+            .iter()
+            .find(|t| Acl::topic_prefix(t).trim_end_matches('.') == namespace)
+            .cloned()
+            .ok_or_else(|| invalid("unknown namespace"))?;
+        let role = role.parse::<ComponentRole>().map_err(|_| invalid("unknown component role"))?;
+        Ok(Self {
+            message_type,
+            key: RoutingKey {
+                tenant: tenant.to_string(),
+                site: site.to_string(),
+                role,
+                subject: subject.to_string(),
+            },
+        })
+    }
+}
+
+impl fmt::Display for RoutedTopic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}.{}.{}.{}",
+            Acl::topic_prefix(&self.message_type),
+            self.key.tenant,
+            self.key.site,
+            self.key.role.as_str(),
+            self.key.subject
+        )
+    }
+}
+
+/// Builds a routed topic for publishing (see BusClient::topic).
+#[derive(Debug, Clone)]
+pub struct TopicBuilder {
+    message_type: MessageType,
+    tenant: Option<String>,
+    site: Option<String>,
+    role: Option<ComponentRole>,
+    subject: Vec<String>,
+}
+
+impl TopicBuilder {
+    pub fn new(message_type: MessageType) -> Self {
+        Self { message_type, tenant: None, site: None, role: None, subject: Vec::new() }
+    }
+
+    pub fn tenant(mut self, tenant: impl Into<String>) -> Self {
+        self.tenant = Some(tenant.into());
+        self
+    }
+
+    pub fn site(mut self, site: impl Into<String>) -> Self {
+        self.site = Some(site.into());
+        self
+    }
+
+    pub fn role(mut self, role: ComponentRole) -> Self {
+        self.role = Some(role);
+        self
+    }
+
+    /// Append subject segment(s); may itself be dotted.
+    pub fn subject(mut self, subject: impl Into<String>) -> Self {
+        self.subject.push(subject.into());
+        self
+    }
+
+    pub fn build(self) -> Result<String, TopicError> {
+        let key = RoutingKey {
+            tenant: self.tenant.ok_or(TopicError::Missing("tenant"))?,
+            site: self.site.ok_or(TopicError::Missing("site"))?,
+            role: self.role.ok_or(TopicError::Missing("role"))?,
+            subject: self.subject.join("."),
+        };
+        if self.subject.is_empty() {
+            return Err(TopicError::Missing("subject"));
+        }
+        // A dot in tenant or site would shift every later position
+        for (position, value) in [("tenant", &key.tenant), ("site", &key.site)] {
+            check_segment(value).map_err(|reason| {
+                TopicError::InvalidTopic(format!("{}={}", position, value), reason)
+            })?;
+        }
+        let topic = RoutedTopic { message_type: self.message_type, key }.to_string();
+        validate_topic(&topic)?;
+        Ok(topic)
+    }
+}
+
+/// Builds a subscription pattern scoped by failure domain; unset positions match any value.
+#[derive(Debug, Clone)]
+pub struct SubscriptionFilter {
+    message_type: MessageType,
+    tenant: Option<String>,
+    site: Option<String>,
+    role: Option<ComponentRole>,
+    subject: Option<String>,
+}
+
+impl SubscriptionFilter {
+    pub fn new(message_type: MessageType) -> Self {
+        Self { message_type, tenant: None, site: None, role: None, subject: None }
+    }
+
+    pub fn tenant(mut self, tenant: impl Into<String>) -> Self {
+        self.tenant = Some(tenant.into());
+        self
+    }
+
+    pub fn site(mut self, site: impl Into<String>) -> Self {
+        self.site = Some(site.into());
+        self
+    }
+
+    pub fn role(mut self, role: ComponentRole) -> Self {
+        self.role = Some(role);
+        self
+    }
+
+    /// Subject pattern (dotted; may use `*` segments or end in `*`). Unset: any subject.
+    pub fn subject(mut self, subject: impl Into<String>) -> Self {
+        self.subject = Some(subject.into());
+        self
+    }
+
+    pub fn pattern(&self) -> Result<String, TopicError> {
+        let pattern = format!(
+            "{}{}.{}.{}.{}",
+            Acl::topic_prefix(&self.message_type),
+            self.tenant.as_deref().unwrap_or("*"),
+            self.site.as_deref().unwrap_or("*"),
+            self.role.as_ref().map_or("*", |r| r.as_str()),
+            self.subject.as_deref().unwrap_or("*"),
+        );
+        for (position, value) in [("tenant", &self.tenant), ("site", &self.site)] {
+            if let Some(value) = value {
+                check_segment(value).map_err(|reason| {
+                    TopicError::InvalidPattern(pattern.clone(), format!("{}: {}", position, reason))
+                })?;
+            }
+        }
+        validate_pattern(&pattern)?;
+        Ok(pattern)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::topic_matches;
+
+    #[test]
+    fn test_builder_roundtrip() {
+        let topic = TopicBuilder::new(MessageType::AlertPublish)
+            .tenant("acme")
+            .site("dc1")
+            .role(ComponentRole::Agent)
+            .subject("ransomware.encryption")
+            .build()
+            .unwrap();
+        assert_eq!(topic, "alert.acme.dc1.agent.ransomware.encryption");
+        let parsed = RoutedTopic::parse(&topic).unwrap();
+        assert_eq!(parsed.message_type, MessageType::AlertPublish);
+        assert_eq!(parsed.key.role, ComponentRole::Agent);
+        assert_eq!(parsed.key.subject, "ransomware.encryption");
+        assert_eq!(parsed.to_string(), topic);
+    }
+
+    #[test]
+    fn test_builder_rejects_bad_segments() {
+        let base = || TopicBuilder::new(MessageType::TelemetryPublish).site("dc1").role(ComponentRole::DPI).subject("flow");
+        assert_eq!(base().build().unwrap_err(), TopicError::Missing("tenant"));
+        assert!(base().tenant("acme.other").build().is_err());
+        assert!(base().tenant("ac*").build().is_err());
+        assert!(base().tenant("").build().is_err());
+        assert!(TopicBuilder::new(MessageType::TelemetryPublish).tenant("a").site("b").role(ComponentRole::DPI).build().is_err());
+    }
+
+    #[test]
+    fn test_flat_topics_stay_valid() {
+        assert!(validate_topic("telemetry.accepted").is_ok());
+        assert!(!is_routed("telemetry.accepted"));
+        assert!(RoutedTopic::parse("telemetry.accepted").is_err());
+        assert!(validate_topic("alert.").is_err());
+        assert!(validate_topic("alert.*").is_err());
+    }
+
+    #[test]
+    fn test_filter_patterns_scope_deliveries() {
+        let pattern = SubscriptionFilter::new(MessageType::AlertPublish)
+            .tenant("acme")
+            .role(ComponentRole::Agent)
+            .pattern()
+            .unwrap();
+        assert_eq!(pattern, "alert.acme.*.agent.*");
+        assert!(topic_matches(&pattern, "alert.acme.dc1.agent.ransomware"));
+        assert!(topic_matches(&pattern, "alert.acme.dc2.agent.ransomware.encryption"));
+        assert!(!topic_matches(&pattern, "alert.globex.dc1.agent.ransomware"));
+        assert!(!topic_matches(&pattern, "alert.acme.dc1.dpi.ransomware"));
+
+        let site = SubscriptionFilter::new(MessageType::TelemetryPublish).site("dc1").subject("process").pattern().unwrap();
+        assert!(topic_matches(&site, "telemetry.acme.dc1.agent.process"));
+        assert!(!topic_matches(&site, "telemetry.acme.dc1.agent.process.start"));
+        assert!(SubscriptionFilter::new(MessageType::AlertPublish).tenant("a.b").pattern().is_err());
+    }
+
+    #[test]
+    fn test_pattern_validation() {
+        assert!(validate_pattern("*").is_ok());
+        assert!(validate_pattern("alert.*").is_ok());
+        assert!(validate_pattern("alert.hi*").is_ok());
+        assert!(validate_pattern("alert.*.dc1.*.*").is_ok());
+        assert!(validate_pattern("alert.a*b").is_err());
+        assert!(validate_pattern("alert.hi*.x").is_err());
+        assert!(validate_pattern("alert..x").is_err());
+    }
+}
@@ -0,0 +1,166 @@
+// Path and File Name : /home/ransomeye/rebuild/core/crypto/src/backend_fips.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: FIPS crypto backend - every primitive from the FIPS 140-3 validated AWS-LC module (aws-lc-rs with the fips feature)
+
+use std::num::NonZeroU32;
+
+use aws_lc_rs::rand::{SecureRandom, SystemRandom};
+use aws_lc_rs::signature::{self as lc_signature, KeyPair};
+use aws_lc_rs::{constant_time, digest, hkdf, hmac, pbkdf2};
+
+use crate::CryptoError;
+
+pub const NAME: &str = "fips (aws-lc-rs)";
+
+/// The module must report FIPS mode; a non-FIPS AWS-LC build is refused
+pub fn self_check() -> Result<(), CryptoError> {
+    aws_lc_rs::try_fips_mode().map_err(|e| CryptoError::SelfCheckFailed(format!("AWS-LC not in FIPS mode: {}", e)))
+}
+
+#[derive(Clone)]
+pub struct Sha256Ctx(digest::Context);
+
+impl Sha256Ctx {
+    pub fn new() -> Self {
+        Self(digest::Context::new(&digest::SHA256))
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    pub fn finish(self) -> [u8; 32] {
+        self.0.finish().as_ref().try_into().expect("SHA-256 digest is 32 bytes")
+    }
+}
+
+pub struct Ed25519Key(lc_signature::Ed25519KeyPair);
+
+impl Ed25519Key {
+    pub fn from_seed(seed: &[u8; 32]) -> Result<Self, CryptoError> {
+        lc_signature::Ed25519KeyPair::from_seed_unchecked(seed)
+            .map(Self)
+            .map_err(|e| CryptoError::InvalidKey(format!("Ed25519 seed rejected: {}", e)))
+    }
+
+    pub fn sign(&self, message: &[u8]) -> [u8; 64] {
+        self.0.sign(message).as_ref().try_into().expect("Ed25519 signature is 64 bytes")
+    }
+
+    pub fn public_key(&self) -> [u8; 32] {
+        self.0.public_key().as_ref().try_into().expect("Ed25519 public key is 32 bytes")
+    }
+}
+
+pub fn ed25519_verify(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    lc_signature::UnparsedPublicKey::new(&lc_signature::ED25519, public_key)
+        .verify(message, signature)
+        .is_ok()
+}
+
+pub fn rsa_pss_sha256_verify(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    lc_signature::UnparsedPublicKey::new(&lc_signature::RSA_PSS_2048_8192_SHA256, public_key)
+        .verify(message, signature)
+        .is_ok()
+}
+
+pub fn rsa_pkcs1_sha256_verify(n: &[u8], e: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    lc_signature::RsaPublicKeyComponents { n, e }
+        .verify(&lc_signature::RSA_PKCS1_2048_8192_SHA256, message, signature)
+        .is_ok()
+}
+
+pub struct RsaKey(lc_signature::RsaKeyPair);
+
+impl RsaKey {
+    pub fn from_pkcs8(der: &[u8]) -> Result<Self, CryptoError> {
+        lc_signature::RsaKeyPair::from_pkcs8(der)
+            .map(Self)
+            .map_err(|e| CryptoError::InvalidKey(format!("RSA PKCS#8 key rejected: {}", e)))
+    }
+
+    pub fn public_key(&self) -> Vec<u8> {
+        self.0.public_key().as_ref().to_vec()
+    }
+
+    pub fn modulus_len(&self) -> usize {
+        self.0.public_modulus_len()
+    }
+
+    pub fn sign_pss_sha256(&self, message: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let mut signature = vec![0u8; self.modulus_len()];
+        self.0
+            .sign(&lc_signature::RSA_PSS_SHA256, &SystemRandom::new(), message, &mut signature)
+            .map_err(|_| CryptoError::InvalidKey("RSA-PSS signing failed".to_string()))?;
+        Ok(signature)
+    }
+}
+
+/// Generated inside the module (FIPS 186-5 key generation); `keygen` is not needed here
+pub fn rsa_generate_pkcs8(bits: usize) -> Result<Vec<u8>, CryptoError> {
+    use aws_lc_rs::encoding::{AsDer, Pkcs8V1Der};
+    use aws_lc_rs::rsa::KeySize;
+
+    let size = match bits {
+        2048 => KeySize::Rsa2048,
+        3072 => KeySize::Rsa3072,
+        4096 => KeySize::Rsa4096,
+        _ => return Err(CryptoError::InvalidKey(format!("Unsupported RSA key size {}", bits))),
+    };
+    let key = lc_signature::RsaKeyPair::generate(size)
+        .map_err(|e| CryptoError::InvalidKey(format!("RSA-{} generation failed: {}", bits, e)))?;
+    let der: Pkcs8V1Der = key
+        .as_der()
+        .map_err(|e| CryptoError::InvalidKey(format!("RSA PKCS#8 encoding failed: {}", e)))?;
+    Ok(der.as_ref().to_vec())
+}
+
+#[derive(Clone)]
+pub struct HmacKey(hmac::Key);
+
+impl HmacKey {
+    pub fn new(key: &[u8]) -> Self {
+        Self(hmac::Key::new(hmac::HMAC_SHA256, key))
+    }
+
+    pub fn sign(&self, parts: &[&[u8]]) -> [u8; 32] {
+        let mut ctx = hmac::Context::with_key(&self.0);
+        for part in parts {
+            ctx.update(part);
+        }
+        ctx.sign().as_ref().try_into().expect("HMAC-SHA256 tag is 32 bytes")
+    }
+
+    pub fn verify(&self, message: &[u8], tag: &[u8]) -> bool {
+        hmac::verify(&self.0, message, tag).is_ok()
+    }
+}
+
+struct OutputLen(usize);
+
+impl hkdf::KeyType for OutputLen {
+    fn len(&self) -> usize {
+        self.0
+    }
+}
+
+pub fn hkdf_sha256(salt: &[u8], ikm: &[u8], info: &[u8], out: &mut [u8]) -> Result<(), CryptoError> {
+    let info = [info];
+    hkdf::Salt::new(hkdf::HKDF_SHA256, salt)
+        .extract(ikm)
+        .expand(&info, OutputLen(out.len()))
+        .and_then(|okm| okm.fill(out))
+        .map_err(|_| CryptoError::KeyDerivationFailed(format!("invalid output length {}", out.len())))
+}
+
+pub fn pbkdf2_sha256(iterations: NonZeroU32, salt: &[u8], password: &[u8], out: &mut [u8]) {
+    pbkdf2::derive(pbkdf2::PBKDF2_HMAC_SHA256, iterations, salt, password, out);
+}
+
+pub fn fill_random(out: &mut [u8]) -> Result<(), CryptoError> {
+    SystemRandom::new().fill(out).map_err(|_| CryptoError::RandomFailed)
+}
+
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    constant_time::verify_slices_are_equal(a, b).is_ok()
+}
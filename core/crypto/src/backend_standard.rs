@@ -0,0 +1,169 @@
+// Path and File Name : /home/ransomeye/rebuild/core/crypto/src/backend_standard.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Standard crypto backend - sha2 (SHA-256), ed25519-dalek (Ed25519), ring (RSA-PSS, RSA PKCS#1, HMAC, HKDF, PBKDF2, randomness), rsa (RSA key generation, `keygen` feature)
+
+use std::num::NonZeroU32;
+
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::{hkdf, hmac, pbkdf2, signature as ring_signature};
+use sha2::Digest;
+
+use crate::CryptoError;
+
+pub const NAME: &str = "standard (ring/ed25519-dalek/sha2)";
+
+pub fn self_check() -> Result<(), CryptoError> {
+    Ok(())
+}
+
+#[derive(Clone)]
+pub struct Sha256Ctx(sha2::Sha256);
+
+impl Sha256Ctx {
+    pub fn new() -> Self {
+        Self(sha2::Sha256::new())
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    pub fn finish(self) -> [u8; 32] {
+        self.0.finalize().into()
+    }
+}
+
+pub struct Ed25519Key(SigningKey);
+
+impl Ed25519Key {
+    pub fn from_seed(seed: &[u8; 32]) -> Result<Self, CryptoError> {
+        Ok(Self(SigningKey::from_bytes(seed)))
+    }
+
+    pub fn sign(&self, message: &[u8]) -> [u8; 64] {
+        self.0.sign(message).to_bytes()
+    }
+
+    pub fn public_key(&self) -> [u8; 32] {
+        self.0.verifying_key().to_bytes()
+    }
+}
+
+pub fn ed25519_verify(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    let Ok(public_key) = <[u8; 32]>::try_from(public_key) else { return false };
+    let Ok(verifying_key) = VerifyingKey::from_bytes(&public_key) else { return false };
+    let Ok(signature) = Signature::from_slice(signature) else { return false };
+    verifying_key.verify(message, &signature).is_ok()
+}
+
+pub fn rsa_pss_sha256_verify(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    ring_signature::UnparsedPublicKey::new(&ring_signature::RSA_PSS_2048_8192_SHA256, public_key)
+        .verify(message, signature)
+        .is_ok()
+}
+
+pub fn rsa_pkcs1_sha256_verify(n: &[u8], e: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    ring_signature::RsaPublicKeyComponents { n, e }
+        .verify(&ring_signature::RSA_PKCS1_2048_8192_SHA256, message, signature)
+        .is_ok()
+}
+
+pub struct RsaKey(ring_signature::RsaKeyPair);
+
+impl RsaKey {
+    pub fn from_pkcs8(der: &[u8]) -> Result<Self, CryptoError> {
+        ring_signature::RsaKeyPair::from_pkcs8(der)
+            .map(Self)
+            .map_err(|e| CryptoError::InvalidKey(format!("RSA PKCS#8 key rejected: {}", e)))
+    }
+
+    pub fn public_key(&self) -> Vec<u8> {
+        self.0.public().as_ref().to_vec()
+    }
+
+    pub fn modulus_len(&self) -> usize {
+        self.0.public().modulus_len()
+    }
+
+    pub fn sign_pss_sha256(&self, message: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let mut signature = vec![0u8; self.modulus_len()];
+        self.0
+            .sign(&ring_signature::RSA_PSS_SHA256, &SystemRandom::new(), message, &mut signature)
+            .map_err(|_| CryptoError::InvalidKey("RSA-PSS signing failed".to_string()))?;
+        Ok(signature)
+    }
+}
+
+/// ring cannot generate RSA keys; the RustCrypto `rsa` crate does it behind `keygen`
+#[cfg(feature = "keygen")]
+pub fn rsa_generate_pkcs8(bits: usize) -> Result<Vec<u8>, CryptoError> {
+    use rsa::pkcs8::EncodePrivateKey;
+
+    let key = rsa::RsaPrivateKey::new(&mut rsa::rand_core::OsRng, bits)
+        .map_err(|e| CryptoError::InvalidKey(format!("RSA-{} generation failed: {}", bits, e)))?;
+    let der = key
+        .to_pkcs8_der()
+        .map_err(|e| CryptoError::InvalidKey(format!("RSA PKCS#8 encoding failed: {}", e)))?;
+    Ok(der.as_bytes().to_vec())
+}
+
+#[cfg(not(feature = "keygen"))]
+pub fn rsa_generate_pkcs8(_bits: usize) -> Result<Vec<u8>, CryptoError> {
+    Err(CryptoError::InvalidKey("RSA key generation needs the crypto `keygen` feature".to_string()))
+}
+
+#[derive(Clone)]
+pub struct HmacKey(hmac::Key);
+
+impl HmacKey {
+    pub fn new(key: &[u8]) -> Self {
+        Self(hmac::Key::new(hmac::HMAC_SHA256, key))
+    }
+
+    pub fn sign(&self, parts: &[&[u8]]) -> [u8; 32] {
+        let mut ctx = hmac::Context::with_key(&self.0);
+        for part in parts {
+            ctx.update(part);
+        }
+        ctx.sign().as_ref().try_into().expect("HMAC-SHA256 tag is 32 bytes")
+    }
+
+    pub fn verify(&self, message: &[u8], tag: &[u8]) -> bool {
+        hmac::verify(&self.0, message, tag).is_ok()
+    }
+}
+
+struct OutputLen(usize);
+
+impl hkdf::KeyType for OutputLen {
+    fn len(&self) -> usize {
+        self.0
+    }
+}
+
+pub fn hkdf_sha256(salt: &[u8], ikm: &[u8], info: &[u8], out: &mut [u8]) -> Result<(), CryptoError> {
+    let info = [info];
+    hkdf::Salt::new(hkdf::HKDF_SHA256, salt)
+        .extract(ikm)
+        .expand(&info, OutputLen(out.len()))
+        .and_then(|okm| okm.fill(out))
+        .map_err(|_| CryptoError::KeyDerivationFailed(format!("invalid output length {}", out.len())))
+}
+
+pub fn pbkdf2_sha256(iterations: NonZeroU32, salt: &[u8], password: &[u8], out: &mut [u8]) {
+    pbkdf2::derive(pbkdf2::PBKDF2_HMAC_SHA256, iterations, salt, password, out);
+}
+
+pub fn fill_random(out: &mut [u8]) -> Result<(), CryptoError> {
+    SystemRandom::new().fill(out).map_err(|_| CryptoError::RandomFailed)
+}
+
+/// ring only offers this under a deprecated module; lengths are not secret here
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let diff = a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y));
+    std::hint::black_box(diff) == 0
+}
@@ -0,0 +1,61 @@
+// Path and File Name : /home/ransomeye/rebuild/core/crypto/src/digest.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: SHA-256 through the selected crypto backend
+
+use crate::backend;
+
+pub const SHA256_LEN: usize = 32;
+
+/// SHA-256 hasher. Mirrors the parts of `sha2::Sha256` the code base uses (`new`, `update`,
+/// `chain_update`, `finalize`, `digest`), returning plain arrays.
+#[derive(Clone)]
+pub struct Sha256(backend::Sha256Ctx);
+
+impl Sha256 {
+    pub fn new() -> Self {
+        Self(backend::Sha256Ctx::new())
+    }
+
+    pub fn update(&mut self, data: impl AsRef<[u8]>) {
+        self.0.update(data.as_ref());
+    }
+
+    pub fn chain_update(mut self, data: impl AsRef<[u8]>) -> Self {
+        self.update(data);
+        self
+    }
+
+    pub fn finalize(self) -> [u8; SHA256_LEN] {
+        self.0.finish()
+    }
+
+    /// One-shot digest
+    pub fn digest(data: impl AsRef<[u8]>) -> [u8; SHA256_LEN] {
+        Self::new().chain_update(data).finalize()
+    }
+}
+
+impl Default for Sha256 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha256_known_answers_and_streaming() {
+        assert_eq!(
+            crate::hex(&Sha256::digest(b"")),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        let mut hasher = Sha256::new();
+        hasher.update(b"ab");
+        let clone = hasher.clone();
+        hasher.update(b"c");
+        assert_eq!(hasher.finalize(), Sha256::digest(b"abc"));
+        assert_eq!(clone.chain_update("c").finalize(), Sha256::digest(b"abc"));
+    }
+}
@@ -0,0 +1,52 @@
+// Path and File Name : /home/ransomeye/rebuild/core/crypto/src/hmac.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: HMAC-SHA256 through the selected crypto backend
+
+use crate::{backend, CryptoError};
+
+pub const HMAC_SHA256_LEN: usize = 32;
+
+#[derive(Clone)]
+pub struct HmacSha256Key(backend::HmacKey);
+
+impl HmacSha256Key {
+    pub fn new(key: &[u8]) -> Self {
+        Self(backend::HmacKey::new(key))
+    }
+
+    pub fn sign(&self, message: &[u8]) -> [u8; HMAC_SHA256_LEN] {
+        self.0.sign(&[message])
+    }
+
+    /// Tag over the concatenation of `parts` without copying them together
+    pub fn sign_parts(&self, parts: &[&[u8]]) -> [u8; HMAC_SHA256_LEN] {
+        self.0.sign(parts)
+    }
+
+    /// Constant-time tag check
+    pub fn verify(&self, message: &[u8], tag: &[u8]) -> Result<(), CryptoError> {
+        if self.0.verify(message, tag) {
+            Ok(())
+        } else {
+            Err(CryptoError::VerificationFailed)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hmac_sha256_rfc4231_case_2() {
+        let key = HmacSha256Key::new(b"Jefe");
+        let tag = key.sign(b"what do ya want for nothing?");
+        assert_eq!(
+            crate::hex(&tag),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(key.sign_parts(&[b"what do ya ", b"want for nothing?"]), tag);
+        key.verify(b"what do ya want for nothing?", &tag).unwrap();
+        assert!(key.verify(b"what do ya want for nothing!", &tag).is_err());
+    }
+}
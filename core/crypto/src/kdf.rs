@@ -0,0 +1,50 @@
+// Path and File Name : /home/ransomeye/rebuild/core/crypto/src/kdf.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: HKDF-SHA256 and PBKDF2-HMAC-SHA256 key derivation through the selected crypto backend
+
+use std::num::NonZeroU32;
+
+use crate::{backend, CryptoError};
+
+/// HKDF-SHA256 (RFC 5869) to a 32-byte key
+pub fn hkdf_sha256(salt: &[u8], ikm: &[u8], info: &[u8]) -> Result<[u8; 32], CryptoError> {
+    let mut out = [0u8; 32];
+    backend::hkdf_sha256(salt, ikm, info, &mut out)?;
+    Ok(out)
+}
+
+/// PBKDF2-HMAC-SHA256 (RFC 8018) of a password to a 32-byte hash (stored operator credentials)
+pub fn pbkdf2_sha256(iterations: u32, salt: &[u8], password: &[u8]) -> Result<[u8; 32], CryptoError> {
+    let iterations = NonZeroU32::new(iterations)
+        .ok_or_else(|| CryptoError::KeyDerivationFailed("PBKDF2 needs at least one iteration".to_string()))?;
+    let mut out = [0u8; 32];
+    backend::pbkdf2_sha256(iterations, salt, password, &mut out);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hkdf_sha256_rfc5869_case_1() {
+        let ikm = [0x0b; 22];
+        let salt: Vec<u8> = (0x00..=0x0c).collect();
+        let info: Vec<u8> = (0xf0..=0xf9).collect();
+        // First 32 of the 42 OKM bytes
+        assert_eq!(
+            crate::hex(&hkdf_sha256(&salt, &ikm, &info).unwrap()),
+            "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf"
+        );
+    }
+
+    #[test]
+    fn test_pbkdf2_sha256_rfc7914_vector() {
+        // RFC 7914 section 11, first 32 of the 64 bytes
+        assert_eq!(
+            crate::hex(&pbkdf2_sha256(1, b"salt", b"passwd").unwrap()),
+            "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc"
+        );
+        assert!(pbkdf2_sha256(0, b"salt", b"passwd").is_err());
+    }
+}
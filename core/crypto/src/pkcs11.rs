@@ -0,0 +1,383 @@
+// Path and File Name : /home/ransomeye/rebuild/core/crypto/src/pkcs11.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: PKCS#11 (HSM / SoftHSM) signing keys - env configuration, Ed25519 and RSA-PSS-SHA256 signing through a token, fail-closed when the token is unavailable
+
+//! Core signing keys (audit log, policy root) can live in an HSM instead of on disk. The token is
+//! configured through environment variables under a per-key prefix:
+//!
+//! - `<PREFIX>_MODULE`: path to the PKCS#11 module (e.g. `/usr/lib/softhsm/libsofthsm2.so`)
+//! - `<PREFIX>_TOKEN_LABEL`: label of the token holding the key
+//! - `<PREFIX>_KEY_LABEL`: `CKA_LABEL` of the private key (and its public key)
+//! - `<PREFIX>_PIN_FILE`: file holding the user PIN
+//!
+//! The key never leaves the token. Every signature is verified against the token's public key
+//! before it is returned, and a lost session is reopened once; after that signing fails with
+//! [`CryptoError::HsmUnavailable`] and callers refuse the operation (FAIL-CLOSED).
+
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::CryptoError;
+
+/// Token and key selection for one PKCS#11-held key
+#[derive(Clone)]
+pub struct Pkcs11Config {
+    pub module: PathBuf,
+    pub token_label: String,
+    pub key_label: String,
+    #[cfg_attr(not(feature = "pkcs11"), allow(dead_code))]
+    pin: String,
+}
+
+impl fmt::Debug for Pkcs11Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pkcs11Config")
+            .field("module", &self.module)
+            .field("token_label", &self.token_label)
+            .field("key_label", &self.key_label)
+            .field("pin", &"<redacted>")
+            .finish()
+    }
+}
+
+impl Pkcs11Config {
+    /// Read `<prefix>_MODULE`, `_TOKEN_LABEL`, `_KEY_LABEL` and `_PIN_FILE`. `Ok(None)` when
+    /// `<prefix>_MODULE` is unset (no HSM for this key); a partial configuration is an error.
+    pub fn from_env(prefix: &str) -> Result<Option<Self>, CryptoError> {
+        Self::from_vars(prefix, |name| std::env::var(name).ok())
+    }
+
+    fn from_vars(prefix: &str, var: impl Fn(&str) -> Option<String>) -> Result<Option<Self>, CryptoError> {
+        let lookup = |suffix: &str| {
+            var(&format!("{}_{}", prefix, suffix)).filter(|value| !value.trim().is_empty())
+        };
+        let module = match lookup("MODULE") {
+            Some(module) => PathBuf::from(module),
+            None => return Ok(None),
+        };
+        let required = |suffix: &str| {
+            lookup(suffix).ok_or_else(|| {
+                CryptoError::HsmConfig(format!("{}_MODULE is set but {}_{} is not", prefix, prefix, suffix))
+            })
+        };
+        let token_label = required("TOKEN_LABEL")?;
+        let key_label = required("KEY_LABEL")?;
+        let pin_file = required("PIN_FILE")?;
+        let pin = std::fs::read_to_string(&pin_file)
+            .map_err(|e| CryptoError::HsmConfig(format!("Failed to read PIN file {}: {}", pin_file, e)))?;
+        let pin = pin.trim_end_matches(['\r', '\n']).to_string();
+        if pin.is_empty() {
+            return Err(CryptoError::HsmConfig(format!("PIN file {} is empty", pin_file)));
+        }
+        Ok(Some(Self { module, token_label, key_label, pin }))
+    }
+}
+
+/// Algorithm of a token-held key, from its `CKA_KEY_TYPE`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pkcs11KeyKind {
+    /// CKK_EC_EDWARDS, signed with CKM_EDDSA
+    Ed25519,
+    /// CKK_RSA, signed with CKM_SHA256_RSA_PKCS_PSS (MGF1-SHA256, 32-byte salt)
+    RsaPssSha256,
+}
+
+#[cfg(feature = "pkcs11")]
+pub use token::Pkcs11Key;
+
+#[cfg(feature = "pkcs11")]
+mod token {
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use std::sync::{Mutex, OnceLock};
+
+    use cryptoki::context::{CInitializeArgs, Pkcs11};
+    use cryptoki::mechanism::rsa::{PkcsMgfType, PkcsPssParams};
+    use cryptoki::mechanism::{Mechanism, MechanismType};
+    use cryptoki::object::{Attribute, AttributeType, KeyType, ObjectClass, ObjectHandle};
+    use cryptoki::session::{Session, UserType};
+    use cryptoki::types::AuthPin;
+
+    use super::{ed25519_public_key, rsa_public_key_der, Pkcs11Config, Pkcs11KeyKind};
+    use crate::signature::{verify_ed25519, verify_rsa_pss_sha256};
+    use crate::CryptoError;
+
+    /// A module may only be initialized once per process; keys on the same module share it
+    fn context(config: &Pkcs11Config) -> Result<Pkcs11, CryptoError> {
+        static CONTEXTS: OnceLock<Mutex<HashMap<PathBuf, Pkcs11>>> = OnceLock::new();
+        let mut contexts = CONTEXTS.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(ctx) = contexts.get(&config.module) {
+            return Ok(ctx.clone());
+        }
+        let ctx = Pkcs11::new(&config.module).map_err(|e| {
+            CryptoError::HsmUnavailable(format!("Failed to load PKCS#11 module {}: {}", config.module.display(), e))
+        })?;
+        ctx.initialize(CInitializeArgs::OsThreads)
+            .map_err(|e| CryptoError::HsmUnavailable(format!("C_Initialize failed: {}", e)))?;
+        contexts.insert(config.module.clone(), ctx.clone());
+        Ok(ctx)
+    }
+
+    struct Open {
+        session: Session,
+        private_key: ObjectHandle,
+    }
+
+    /// Signing key held in a PKCS#11 token
+    pub struct Pkcs11Key {
+        config: Pkcs11Config,
+        kind: Pkcs11KeyKind,
+        public_key: Vec<u8>,
+        open: Mutex<Option<Open>>,
+    }
+
+    impl Pkcs11Key {
+        /// Log into the token and locate the key pair. FAIL-CLOSED: an absent token, wrong PIN or
+        /// missing key is an error, never a fallback to a software key.
+        pub fn open(config: &Pkcs11Config) -> Result<Self, CryptoError> {
+            let (open, kind, public_key) = Self::connect(config)?;
+            Ok(Self { config: config.clone(), kind, public_key, open: Mutex::new(Some(open)) })
+        }
+
+        pub fn kind(&self) -> Pkcs11KeyKind {
+            self.kind
+        }
+
+        /// Raw 32-byte key (Ed25519) or DER `RSAPublicKey` (RSA), as the verifiers expect
+        pub fn public_key(&self) -> &[u8] {
+            &self.public_key
+        }
+
+        pub fn config(&self) -> &Pkcs11Config {
+            &self.config
+        }
+
+        /// Sign `message` on the token. A failed attempt reopens the session once (token
+        /// re-inserted, HSM failover); the signature is checked against the public key before it
+        /// is returned.
+        pub fn sign(&self, message: &[u8]) -> Result<Vec<u8>, CryptoError> {
+            let mut open = self.open.lock().unwrap_or_else(|e| e.into_inner());
+            let signature = match open.as_ref().map(|o| self.sign_with(o, message)) {
+                Some(Ok(signature)) => signature,
+                _ => {
+                    *open = None;
+                    let (reopened, kind, public_key) = Self::connect(&self.config)?;
+                    if kind != self.kind || public_key != self.public_key {
+                        return Err(CryptoError::HsmUnavailable(format!(
+                            "Key '{}' on token '{}' changed since startup",
+                            self.config.key_label, self.config.token_label
+                        )));
+                    }
+                    let signature = self.sign_with(&reopened, message)?;
+                    *open = Some(reopened);
+                    signature
+                }
+            };
+            let verified = match self.kind {
+                Pkcs11KeyKind::Ed25519 => verify_ed25519(&self.public_key, message, &signature),
+                Pkcs11KeyKind::RsaPssSha256 => verify_rsa_pss_sha256(&self.public_key, message, &signature),
+            };
+            verified.map_err(|_| {
+                CryptoError::HsmUnavailable("Token returned a signature that does not verify".to_string())
+            })?;
+            Ok(signature)
+        }
+
+        fn sign_with(&self, open: &Open, message: &[u8]) -> Result<Vec<u8>, CryptoError> {
+            let mechanism = match self.kind {
+                Pkcs11KeyKind::Ed25519 => Mechanism::Eddsa,
+                Pkcs11KeyKind::RsaPssSha256 => Mechanism::Sha256RsaPkcsPss(PkcsPssParams {
+                    hash_alg: MechanismType::SHA256,
+                    mgf: PkcsMgfType::MGF1_SHA256,
+                    s_len: 32.into(),
+                }),
+            };
+            open.session
+                .sign(&mechanism, open.private_key, message)
+                .map_err(|e| CryptoError::HsmUnavailable(format!("C_Sign failed: {}", e)))
+        }
+
+        fn connect(config: &Pkcs11Config) -> Result<(Open, Pkcs11KeyKind, Vec<u8>), CryptoError> {
+            let unavailable = |what: &str, e: cryptoki::error::Error| {
+                CryptoError::HsmUnavailable(format!("{} (token '{}'): {}", what, config.token_label, e))
+            };
+            let ctx = context(config)?;
+            let slot = ctx
+                .get_slots_with_token()
+                .map_err(|e| unavailable("Failed to list slots", e))?
+                .into_iter()
+                .find(|slot| {
+                    ctx.get_token_info(*slot)
+                        .map(|info| info.label().trim_end() == config.token_label)
+                        .unwrap_or(false)
+                })
+                .ok_or_else(|| CryptoError::HsmUnavailable(format!("Token '{}' not present", config.token_label)))?;
+            let session = ctx.open_ro_session(slot).map_err(|e| unavailable("Failed to open session", e))?;
+            session
+                .login(UserType::User, Some(&AuthPin::new(config.pin.clone())))
+                .map_err(|e| unavailable("Login failed", e))?;
+
+            let find = |class: ObjectClass| -> Result<ObjectHandle, CryptoError> {
+                let template = [Attribute::Class(class), Attribute::Label(config.key_label.as_bytes().to_vec())];
+                let mut found = session.find_objects(&template).map_err(|e| unavailable("Key lookup failed", e))?;
+                match found.len() {
+                    1 => Ok(found.remove(0)),
+                    0 => Err(CryptoError::HsmUnavailable(format!(
+                        "Key '{}' not found on token '{}'",
+                        config.key_label, config.token_label
+                    ))),
+                    n => Err(CryptoError::HsmConfig(format!(
+                        "Key label '{}' is ambiguous on token '{}' ({} objects)",
+                        config.key_label, config.token_label, n
+                    ))),
+                }
+            };
+            let private_key = find(ObjectClass::PRIVATE_KEY)?;
+            let public_handle = find(ObjectClass::PUBLIC_KEY)?;
+
+            let attributes = session
+                .get_attributes(
+                    public_handle,
+                    &[AttributeType::KeyType, AttributeType::EcPoint, AttributeType::Modulus, AttributeType::PublicExponent],
+                )
+                .map_err(|e| unavailable("Failed to read public key", e))?;
+            let mut key_type = None;
+            let (mut ec_point, mut modulus, mut exponent) = (None, None, None);
+            for attribute in attributes {
+                match attribute {
+                    Attribute::KeyType(kt) => key_type = Some(kt),
+                    Attribute::EcPoint(point) => ec_point = Some(point),
+                    Attribute::Modulus(n) => modulus = Some(n),
+                    Attribute::PublicExponent(e) => exponent = Some(e),
+                    _ => {}
+                }
+            }
+            let (kind, public_key) = match (key_type, ec_point, modulus, exponent) {
+                (Some(KeyType::EC_EDWARDS), Some(point), _, _) => {
+                    (Pkcs11KeyKind::Ed25519, ed25519_public_key(&point)?.to_vec())
+                }
+                (Some(KeyType::RSA), _, Some(n), Some(e)) => (Pkcs11KeyKind::RsaPssSha256, rsa_public_key_der(&n, &e)),
+                (kt, ..) => {
+                    return Err(CryptoError::InvalidKey(format!(
+                        "Key '{}' has unsupported type {:?} (expected Ed25519 or RSA)",
+                        config.key_label, kt
+                    )))
+                }
+            };
+            Ok((Open { session, private_key }, kind, public_key))
+        }
+    }
+}
+
+/// Raw Ed25519 key from `CKA_EC_POINT`: a DER OCTET STRING per PKCS#11 3.0, raw bytes on some
+/// older tokens
+#[cfg_attr(not(feature = "pkcs11"), allow(dead_code))]
+fn ed25519_public_key(ec_point: &[u8]) -> Result<[u8; 32], CryptoError> {
+    let raw = match ec_point {
+        [0x04, 0x20, rest @ ..] if rest.len() == 32 => rest,
+        raw if raw.len() == 32 => raw,
+        _ => return Err(CryptoError::InvalidKey(format!("Unexpected Ed25519 EC point ({} bytes)", ec_point.len()))),
+    };
+    let mut key = [0u8; 32];
+    key.copy_from_slice(raw);
+    Ok(key)
+}
+
+/// DER `RSAPublicKey ::= SEQUENCE { modulus INTEGER, publicExponent INTEGER }` from the big-endian
+/// `CKA_MODULUS` / `CKA_PUBLIC_EXPONENT`
+#[cfg_attr(not(feature = "pkcs11"), allow(dead_code))]
+fn rsa_public_key_der(modulus: &[u8], exponent: &[u8]) -> Vec<u8> {
+    fn push_len(out: &mut Vec<u8>, len: usize) {
+        if len < 0x80 {
+            out.push(len as u8);
+        } else {
+            let bytes: Vec<u8> = len.to_be_bytes().into_iter().skip_while(|b| *b == 0).collect();
+            out.push(0x80 | bytes.len() as u8);
+            out.extend_from_slice(&bytes);
+        }
+    }
+    fn integer(value: &[u8]) -> Vec<u8> {
+        let trimmed: &[u8] = match value.iter().position(|b| *b != 0) {
+            Some(first) => &value[first..],
+            None => &[0],
+        };
+        let pad = trimmed[0] & 0x80 != 0;
+        let mut out = vec![0x02];
+        push_len(&mut out, trimmed.len() + pad as usize);
+        if pad {
+            out.push(0);
+        }
+        out.extend_from_slice(trimmed);
+        out
+    }
+    let body = [integer(modulus), integer(exponent)].concat();
+    let mut out = vec![0x30];
+    push_len(&mut out, body.len());
+    out.extend_from_slice(&body);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn vars(pairs: &[(&str, String)]) -> impl Fn(&str) -> Option<String> {
+        let map: HashMap<String, String> = pairs.iter().map(|(k, v)| (k.to_string(), v.clone())).collect();
+        move |name| map.get(name).cloned()
+    }
+
+    #[test]
+    fn test_config_absent_without_module() {
+        assert!(Pkcs11Config::from_vars("RANSOMEYE_AUDIT_PKCS11", vars(&[])).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_config_from_vars() {
+        let dir = std::env::temp_dir().join(format!("ransomeye-pkcs11-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let pin_file = dir.join("pin");
+        std::fs::write(&pin_file, "1234\n").unwrap();
+
+        let config = Pkcs11Config::from_vars(
+            "P",
+            vars(&[
+                ("P_MODULE", "/usr/lib/softhsm/libsofthsm2.so".to_string()),
+                ("P_TOKEN_LABEL", "ransomeye".to_string()),
+                ("P_KEY_LABEL", "audit".to_string()),
+                ("P_PIN_FILE", pin_file.display().to_string()),
+            ]),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(config.token_label, "ransomeye");
+        assert_eq!(config.pin, "1234");
+        assert!(!format!("{:?}", config).contains("1234"));
+
+        let partial = Pkcs11Config::from_vars("P", vars(&[("P_MODULE", "/lib.so".to_string())]));
+        assert!(matches!(partial, Err(CryptoError::HsmConfig(_))));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_ed25519_ec_point_forms() {
+        let raw = [7u8; 32];
+        let mut der = vec![0x04, 0x20];
+        der.extend_from_slice(&raw);
+        assert_eq!(ed25519_public_key(&der).unwrap(), raw);
+        assert_eq!(ed25519_public_key(&raw).unwrap(), raw);
+        assert!(ed25519_public_key(&[0x04, 0x01, 0x00]).is_err());
+    }
+
+    #[test]
+    fn test_rsa_public_key_der() {
+        // 0x80.. modulus needs a leading zero; 65537 does not
+        let der = rsa_public_key_der(&[0x80, 0x01], &[0x01, 0x00, 0x01]);
+        assert_eq!(der, vec![0x30, 0x0a, 0x02, 0x03, 0x00, 0x80, 0x01, 0x02, 0x03, 0x01, 0x00, 0x01]);
+
+        let modulus = vec![0xc5; 512];
+        let der = rsa_public_key_der(&modulus, &[0x01, 0x00, 0x01]);
+        assert_eq!(&der[..4], &[0x30, 0x82, 0x02, 0x0a]);
+        assert_eq!(&der[4..8], &[0x02, 0x82, 0x02, 0x01]);
+    }
+}
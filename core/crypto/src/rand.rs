@@ -0,0 +1,10 @@
+// Path and File Name : /home/ransomeye/rebuild/core/crypto/src/rand.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Cryptographically secure randomness through the selected crypto backend
+
+use crate::{backend, CryptoError};
+
+/// Fill `out` from the backend's DRBG (nonces, secrets, key seeds)
+pub fn fill(out: &mut [u8]) -> Result<(), CryptoError> {
+    backend::fill_random(out)
+}
@@ -0,0 +1,160 @@
+// Path and File Name : /home/ransomeye/rebuild/core/crypto/src/signature.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Ed25519 signing/verification, RSA-PSS signing/verification and RSA PKCS#1 v1.5 verification through the selected crypto backend
+
+use crate::{backend, kdf, rand, CryptoError};
+
+pub const ED25519_PUBLIC_KEY_LEN: usize = 32;
+pub const ED25519_SIGNATURE_LEN: usize = 64;
+
+/// Ed25519 key pair from a raw 32-byte seed (the on-disk format of agent and probe keys)
+pub struct Ed25519KeyPair {
+    key: backend::Ed25519Key,
+    seed: [u8; 32],
+}
+
+impl Ed25519KeyPair {
+    pub fn from_seed(seed: &[u8; 32]) -> Result<Self, CryptoError> {
+        Ok(Self { key: backend::Ed25519Key::from_seed(seed)?, seed: *seed })
+    }
+
+    /// Fresh key pair from the backend's random generator
+    pub fn generate() -> Result<Self, CryptoError> {
+        let mut seed = [0u8; 32];
+        rand::fill(&mut seed)?;
+        Self::from_seed(&seed)
+    }
+
+    pub fn sign(&self, message: &[u8]) -> [u8; ED25519_SIGNATURE_LEN] {
+        self.key.sign(message)
+    }
+
+    pub fn public_key(&self) -> [u8; ED25519_PUBLIC_KEY_LEN] {
+        self.key.public_key()
+    }
+
+    /// 32-byte key for local use, derived from the seed (HKDF-SHA256, empty salt). Stable for as
+    /// long as the key pair is; `context` separates the uses.
+    pub fn derive_key(&self, context: &[u8]) -> Result<[u8; 32], CryptoError> {
+        kdf::hkdf_sha256(&[], &self.seed, context)
+    }
+}
+
+pub fn verify_ed25519(public_key: &[u8], message: &[u8], signature: &[u8]) -> Result<(), CryptoError> {
+    if backend::ed25519_verify(public_key, message, signature) {
+        Ok(())
+    } else {
+        Err(CryptoError::VerificationFailed)
+    }
+}
+
+/// RSA key pair for RSA-PSS-SHA256 signing (policy root), PKCS#8 DER on disk
+pub struct RsaPssKeyPair {
+    key: backend::RsaKey,
+    pkcs8: Vec<u8>,
+}
+
+impl RsaPssKeyPair {
+    pub fn from_pkcs8(der: &[u8]) -> Result<Self, CryptoError> {
+        Ok(Self { key: backend::RsaKey::from_pkcs8(der)?, pkcs8: der.to_vec() })
+    }
+
+    /// Fresh 2048, 3072 or 4096-bit key. The standard backend needs the `keygen` feature.
+    pub fn generate(bits: usize) -> Result<Self, CryptoError> {
+        if !matches!(bits, 2048 | 3072 | 4096) {
+            return Err(CryptoError::InvalidKey(format!("Unsupported RSA key size {}", bits)));
+        }
+        Self::from_pkcs8(&backend::rsa_generate_pkcs8(bits)?)
+    }
+
+    pub fn pkcs8_der(&self) -> &[u8] {
+        &self.pkcs8
+    }
+
+    /// DER `RSAPublicKey`, as [`verify_rsa_pss_sha256`] and the policy trust store expect
+    pub fn public_key(&self) -> Vec<u8> {
+        self.key.public_key()
+    }
+
+    pub fn modulus_bits(&self) -> usize {
+        self.key.modulus_len() * 8
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        self.key.sign_pss_sha256(message)
+    }
+}
+
+/// RSA-PSS (2048-8192 bit, SHA-256) verification; `public_key` as accepted by ring's
+/// `UnparsedPublicKey`
+pub fn verify_rsa_pss_sha256(public_key: &[u8], message: &[u8], signature: &[u8]) -> Result<(), CryptoError> {
+    if backend::rsa_pss_sha256_verify(public_key, message, signature) {
+        Ok(())
+    } else {
+        Err(CryptoError::VerificationFailed)
+    }
+}
+
+/// RSASSA-PKCS1-v1_5 with SHA-256 (JWS `RS256`, 2048-8192 bit) against a key given as its
+/// big-endian modulus and exponent (a JWK's `n` and `e`)
+pub fn verify_rsa_pkcs1_sha256(n: &[u8], e: &[u8], message: &[u8], signature: &[u8]) -> Result<(), CryptoError> {
+    if backend::rsa_pkcs1_sha256_verify(n, e, message, signature) {
+        Ok(())
+    } else {
+        Err(CryptoError::VerificationFailed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "keygen")]
+    #[test]
+    fn test_rsa_pss_generate_sign_verify() {
+        let key = RsaPssKeyPair::generate(2048).unwrap();
+        assert_eq!(key.modulus_bits(), 2048);
+        let sig = key.sign(b"policy").unwrap();
+        verify_rsa_pss_sha256(&key.public_key(), b"policy", &sig).unwrap();
+        assert!(verify_rsa_pss_sha256(&key.public_key(), b"tampered", &sig).is_err());
+
+        let reloaded = RsaPssKeyPair::from_pkcs8(key.pkcs8_der()).unwrap();
+        assert_eq!(reloaded.public_key(), key.public_key());
+        assert!(RsaPssKeyPair::generate(1024).is_err());
+    }
+
+    #[test]
+    fn test_ed25519_rfc8032_vector() {
+        // RFC 8032 section 7.1, TEST 1 (empty message)
+        let seed: [u8; 32] = [
+            0x9d, 0x61, 0xb1, 0x9d, 0xef, 0xfd, 0x5a, 0x60, 0xba, 0x84, 0x4a, 0xf4, 0x92, 0xec, 0x2c, 0xc4,
+            0x44, 0x49, 0xc5, 0x69, 0x7b, 0x32, 0x69, 0x19, 0x70, 0x3b, 0xac, 0x03, 0x1c, 0xae, 0x7f, 0x60,
+        ];
+        let key = Ed25519KeyPair::from_seed(&seed).unwrap();
+        assert_eq!(
+            crate::hex(&key.public_key()),
+            "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a"
+        );
+        let sig = key.sign(b"");
+        assert_eq!(
+            crate::hex(&sig),
+            "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b"
+        );
+        verify_ed25519(&key.public_key(), b"", &sig).unwrap();
+        assert!(verify_ed25519(&key.public_key(), b"x", &sig).is_err());
+        assert!(verify_ed25519(&key.public_key()[..31], b"", &sig).is_err());
+        assert!(verify_ed25519(&key.public_key(), b"", &sig[..63]).is_err());
+    }
+
+    #[test]
+    fn test_derived_keys_are_stable_and_separated() {
+        let key = Ed25519KeyPair::from_seed(&[1u8; 32]).unwrap();
+        let again = Ed25519KeyPair::from_seed(&[1u8; 32]).unwrap();
+        assert_eq!(key.derive_key(b"spool").unwrap(), again.derive_key(b"spool").unwrap());
+        assert_ne!(key.derive_key(b"spool").unwrap(), key.derive_key(b"other").unwrap());
+        assert_ne!(
+            key.derive_key(b"spool").unwrap(),
+            Ed25519KeyPair::from_seed(&[2u8; 32]).unwrap().derive_key(b"spool").unwrap()
+        );
+    }
+}
@@ -0,0 +1,7 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/build.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Build script - embeds build provenance (git commit, Cargo.lock hash, CycloneDX SBOM) read back by build_info::build_info!()
+
+fn main() {
+    build_info::emit();
+}
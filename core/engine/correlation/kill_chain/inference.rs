@@ -6,6 +6,7 @@ use crate::kill_chain::rules::{KillChainRuleEngine, Signal};
 use crate::kill_chain::stages::{RansomwareStage, StageMetadata};
 use crate::kill_chain::transitions::{TransitionResult, TransitionRules};
 use chrono::{DateTime, Utc};
+use ingest::attack::AttackTag;
 
 /// Kill-chain inference result
 #[derive(Debug, Clone)]
@@ -20,6 +21,10 @@ pub struct InferenceResult {
     pub transition_valid: bool,
     /// Rationale for inference
     pub rationale: String,
+    /// Rule that matched
+    pub rule_id: String,
+    /// ATT&CK techniques of that rule
+    pub attack: Vec<AttackTag>,
 }
 
 /// Kill-chain inference engine
@@ -48,7 +53,8 @@ impl KillChainInferencer {
         }
 
         // Use rule engine to find matching stage
-        if let Some((stage, confidence)) = self.rule_engine.infer_stage(current_stage, signals) {
+        if let Some((rule, confidence)) = self.rule_engine.infer_rule(current_stage, signals) {
+            let stage = rule.target_stage;
             // Validate transition
             let transition_result = self
                 .transition_rules
@@ -79,6 +85,8 @@ impl KillChainInferencer {
                 contributing_signals,
                 transition_valid: true,
                 rationale,
+                rule_id: rule.id.clone(),
+                attack: rule.attack.clone(),
             })
         } else {
             None
@@ -156,6 +164,8 @@ mod tests {
         assert_eq!(result.stage, RansomwareStage::InitialAccess);
         assert!(result.confidence >= 0.6);
         assert!(!result.contributing_signals.is_empty());
+        assert_eq!(result.rule_id, "initial_access_1");
+        assert!(result.attack.is_empty());
     }
 
     #[test]
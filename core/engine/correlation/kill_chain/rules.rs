@@ -5,6 +5,7 @@
 use crate::kill_chain::stages::{RansomwareStage, StageMetadata};
 use crate::kill_chain::transitions::TransitionRules;
 use chrono::{DateTime, Utc};
+use ingest::attack::AttackTag;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -36,6 +37,23 @@ pub struct KillChainRule {
     pub min_confidence: f64,
     /// Temporal constraints
     pub temporal_constraints: Option<TemporalConstraint>,
+    /// MITRE ATT&CK techniques a match is evidence of (empty = untagged)
+    #[serde(default)]
+    pub attack: Vec<AttackTag>,
+}
+
+impl KillChainRule {
+    /// Check the rule's ATT&CK tags
+    pub fn validate_attack(&self) -> Result<(), String> {
+        for tag in &self.attack {
+            tag.validate().map_err(|e| format!("rule {}: {}", self.id, e))?;
+        }
+        Ok(())
+    }
+}
+
+fn attack(technique_id: &str, tactic: &str) -> AttackTag {
+    AttackTag::new(technique_id, tactic).expect("built-in rule tags are valid")
 }
 
 /// Signal pattern matching
@@ -99,6 +117,8 @@ impl KillChainRuleEngine {
                     max_window_seconds: 300,
                     min_interval_seconds: None,
                 }),
+                // A single connection says nothing about how the attacker got in
+                attack: Vec::new(),
             },
             KillChainRule {
                 id: "execution_1".to_string(),
@@ -116,6 +136,7 @@ impl KillChainRuleEngine {
                     max_window_seconds: 60,
                     min_interval_seconds: None,
                 }),
+                attack: vec![attack("T1059", "execution")],
             },
             KillChainRule {
                 id: "encryption_execution_1".to_string(),
@@ -138,6 +159,7 @@ impl KillChainRuleEngine {
                     max_window_seconds: 60,
                     min_interval_seconds: Some(1),
                 }),
+                attack: vec![attack("T1486", "impact")],
             },
             KillChainRule {
                 id: "impact_1".to_string(),
@@ -155,17 +177,33 @@ impl KillChainRuleEngine {
                     max_window_seconds: 300,
                     min_interval_seconds: None,
                 }),
+                attack: vec![attack("T1486", "impact"), attack("T1491.001", "impact")],
             },
         ]
     }
 
+    /// Rules in evaluation order
+    pub fn rules(&self) -> &[KillChainRule] {
+        &self.rules
+    }
+
     /// Evaluate signals against rules to infer stage
     pub fn infer_stage(
         &self,
         current_stage: Option<RansomwareStage>,
         signals: &[Signal],
     ) -> Option<(RansomwareStage, f64)> {
-        let mut best_match: Option<(RansomwareStage, f64)> = None;
+        self.infer_rule(current_stage, signals)
+            .map(|(rule, confidence)| (rule.target_stage, confidence))
+    }
+
+    /// Evaluate signals against rules; the best matching rule and its confidence
+    pub fn infer_rule(
+        &self,
+        current_stage: Option<RansomwareStage>,
+        signals: &[Signal],
+    ) -> Option<(&KillChainRule, f64)> {
+        let mut best_match: Option<(&KillChainRule, f64)> = None;
 
         for rule in &self.rules {
             // Check transition validity
@@ -181,9 +219,9 @@ impl KillChainRuleEngine {
             if let Some(confidence) = self.evaluate_rule(rule, signals) {
                 if confidence >= rule.min_confidence {
                     match best_match {
-                        None => best_match = Some((rule.target_stage, confidence)),
+                        None => best_match = Some((rule, confidence)),
                         Some((_, best_conf)) if confidence > best_conf => {
-                            best_match = Some((rule.target_stage, confidence))
+                            best_match = Some((rule, confidence))
                         }
                         _ => {}
                     }
@@ -323,5 +361,29 @@ mod tests {
         assert_eq!(stage, RansomwareStage::InitialAccess);
         assert!(conf >= 0.6);
     }
+
+    #[test]
+    fn test_default_rules_carry_valid_attack_tags() {
+        let engine = KillChainRuleEngine::new();
+        for rule in engine.rules() {
+            rule.validate_attack().unwrap();
+        }
+        let signals = vec![Signal {
+            signal_type: "ransom_note".to_string(),
+            timestamp: Utc::now(),
+            entity_id: "test".to_string(),
+            confidence: 0.95,
+            metadata: HashMap::new(),
+        }];
+        let (rule, _) = engine.infer_rule(Some(RansomwareStage::EncryptionExecution), &signals).unwrap();
+        assert_eq!(rule.id, "impact_1");
+        let techniques: Vec<&str> = rule.attack.iter().map(|t| t.technique_id.as_str()).collect();
+        assert_eq!(techniques, vec!["T1486", "T1491.001"]);
+
+        // Tags from a rule file are normalized or refused
+        let mut tagged = rule.clone();
+        tagged.attack = vec![AttackTag { technique_id: "T1486".to_string(), tactic: "Impact".to_string() }];
+        assert!(tagged.validate_attack().is_err());
+    }
 }
 
@@ -4,7 +4,9 @@
 
 use crate::explainability::{ExplainabilityArtifact, ConfidenceBreakdown};
 use crate::kill_chain::stages::RansomwareStage;
+use crate::output::enrichment::DetectionEnrichment;
 use chrono::{DateTime, Utc};
+use ingest::attack::AttackTag;
 use serde::{Deserialize, Serialize};
 
 /// Detection result (authoritative output)
@@ -24,6 +26,12 @@ pub struct DetectionResult {
     pub explainability: ExplainabilityArtifact,
     /// Detection metadata
     pub metadata: DetectionMetadata,
+    /// Business context added by enrichers (asset tags, priority)
+    #[serde(default)]
+    pub enrichment: DetectionEnrichment,
+    /// MITRE ATT&CK techniques of the rule that fired
+    #[serde(default)]
+    pub attack: Vec<AttackTag>,
 }
 
 /// Detection metadata
@@ -56,6 +64,8 @@ impl DetectionResult {
             confidence,
             explainability,
             metadata,
+            enrichment: DetectionEnrichment::untagged(confidence),
+            attack: Vec::new(),
         }
     }
 
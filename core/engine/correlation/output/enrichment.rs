@@ -0,0 +1,202 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_core/correlation/output/enrichment.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details: Pluggable detection enrichment - asset criticality tags (criticality, owner, environment) and criticality-weighted priority
+
+use crate::output::detection_result::DetectionResult;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+/// Business criticality of an asset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AssetCriticality {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl AssetCriticality {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "low" => Some(AssetCriticality::Low),
+            "medium" => Some(AssetCriticality::Medium),
+            "high" => Some(AssetCriticality::High),
+            "critical" => Some(AssetCriticality::Critical),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AssetCriticality::Low => "low",
+            AssetCriticality::Medium => "medium",
+            AssetCriticality::High => "high",
+            AssetCriticality::Critical => "critical",
+        }
+    }
+
+    /// Priority weight applied to detection confidence
+    pub fn weight(&self) -> f64 {
+        match self {
+            AssetCriticality::Low => 0.5,
+            AssetCriticality::Medium => UNTAGGED_PRIORITY_WEIGHT,
+            AssetCriticality::High => 0.9,
+            AssetCriticality::Critical => 1.0,
+        }
+    }
+}
+
+/// Untagged assets are prioritized like medium-criticality ones
+pub const UNTAGGED_PRIORITY_WEIGHT: f64 = 0.75;
+
+/// Business context of one asset
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssetTags {
+    pub criticality: AssetCriticality,
+    pub owner: Option<String>,
+    pub environment: Option<String>,
+}
+
+/// Enrichment carried on a detection for notification routing and prioritization
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DetectionEnrichment {
+    /// Asset tags of the detected entity (None = untagged)
+    pub asset: Option<AssetTags>,
+    /// Confidence weighted by asset criticality (0.0-1.0)
+    pub priority: f64,
+    /// Enrichers that touched this detection, in order
+    pub enrichers: Vec<String>,
+}
+
+impl DetectionEnrichment {
+    pub fn untagged(confidence: f64) -> Self {
+        Self {
+            asset: None,
+            priority: confidence * UNTAGGED_PRIORITY_WEIGHT,
+            enrichers: Vec::new(),
+        }
+    }
+}
+
+/// Detection enricher (applied by the engine to every emitted detection)
+pub trait DetectionEnricher: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn enrich(&self, detection: &mut DetectionResult);
+}
+
+/// Asset tag lookup by entity id, IP address or hostname (case-insensitive)
+#[derive(Debug, Default, Clone)]
+pub struct AssetCatalog {
+    by_entity: HashMap<String, AssetTags>,
+    by_ip: HashMap<IpAddr, AssetTags>,
+    by_hostname: HashMap<String, AssetTags>,
+}
+
+impl AssetCatalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert_hostname(&mut self, hostname: &str, tags: AssetTags) {
+        self.by_hostname.insert(hostname.trim().to_ascii_lowercase(), tags);
+    }
+
+    pub fn insert_ip(&mut self, ip: IpAddr, tags: AssetTags) {
+        self.by_ip.insert(ip, tags);
+    }
+
+    /// Entity ids that are neither hostnames nor IPs (e.g. agent ids resolved from the agents table)
+    pub fn insert_entity(&mut self, entity_id: &str, tags: AssetTags) {
+        self.by_entity.insert(entity_id.to_string(), tags);
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_entity.len() + self.by_ip.len() + self.by_hostname.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn lookup(&self, entity_id: &str) -> Option<&AssetTags> {
+        if let Some(tags) = self.by_entity.get(entity_id) {
+            return Some(tags);
+        }
+        if let Ok(ip) = entity_id.parse::<IpAddr>() {
+            return self.by_ip.get(&ip);
+        }
+        let hostname = entity_id.trim().to_ascii_lowercase();
+        self.by_hostname.get(&hostname).or_else(|| {
+            // FQDN entity against a short-name tag
+            hostname.split_once('.').and_then(|(short, _)| self.by_hostname.get(short))
+        })
+    }
+}
+
+/// Enriches detections with asset criticality tags; the catalog can be replaced at runtime
+pub struct AssetTagEnricher {
+    catalog: RwLock<AssetCatalog>,
+}
+
+impl AssetTagEnricher {
+    pub fn new(catalog: AssetCatalog) -> Self {
+        Self { catalog: RwLock::new(catalog) }
+    }
+
+    pub fn replace_catalog(&self, catalog: AssetCatalog) {
+        *self.catalog.write() = catalog;
+    }
+}
+
+impl DetectionEnricher for AssetTagEnricher {
+    fn name(&self) -> &'static str {
+        "asset_tags"
+    }
+
+    fn enrich(&self, detection: &mut DetectionResult) {
+        let asset = self.catalog.read().lookup(&detection.entity_id).cloned();
+        let weight = asset.as_ref().map(|a| a.criticality.weight()).unwrap_or(UNTAGGED_PRIORITY_WEIGHT);
+        detection.enrichment.priority = detection.confidence * weight;
+        detection.enrichment.asset = asset;
+        detection.enrichment.enrichers.push(self.name().to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(criticality: AssetCriticality) -> AssetTags {
+        AssetTags {
+            criticality,
+            owner: Some("finance-it".to_string()),
+            environment: Some("production".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_lookup_by_entity_ip_and_hostname() {
+        let mut catalog = AssetCatalog::new();
+        catalog.insert_hostname("DB01", tags(AssetCriticality::Critical));
+        catalog.insert_ip("10.1.2.3".parse().unwrap(), tags(AssetCriticality::High));
+        catalog.insert_entity("agent-7", tags(AssetCriticality::Low));
+
+        assert_eq!(catalog.lookup("db01").unwrap().criticality, AssetCriticality::Critical);
+        assert_eq!(catalog.lookup("db01.corp.example").unwrap().criticality, AssetCriticality::Critical);
+        assert_eq!(catalog.lookup("10.1.2.3").unwrap().criticality, AssetCriticality::High);
+        assert_eq!(catalog.lookup("agent-7").unwrap().criticality, AssetCriticality::Low);
+        assert!(catalog.lookup("web02").is_none());
+    }
+
+    #[test]
+    fn test_criticality_parse_and_order() {
+        assert_eq!(AssetCriticality::parse(" Critical "), Some(AssetCriticality::Critical));
+        assert_eq!(AssetCriticality::parse("urgent"), None);
+        assert!(AssetCriticality::Critical > AssetCriticality::High);
+        assert!(AssetCriticality::Critical.weight() > UNTAGGED_PRIORITY_WEIGHT);
+        assert!(AssetCriticality::Low.weight() < UNTAGGED_PRIORITY_WEIGHT);
+    }
+}
@@ -4,5 +4,6 @@
 
 pub mod confidence;
 pub mod detection_result;
+pub mod enrichment;
 pub mod rationale;
 
@@ -13,6 +13,7 @@ use crate::kill_chain::inference::KillChainInferencer;
 use crate::kill_chain::rules::Signal as KillChainSignal;
 use crate::kill_chain::stages::RansomwareStage;
 use crate::output::detection_result::{DetectionMetadata, DetectionResult};
+use crate::output::enrichment::DetectionEnricher;
 use crate::scheduler::EntityScheduler;
 use crate::scoring::{ConfidenceScorer, SignalContribution};
 use crate::temporal::TemporalCorrelator;
@@ -74,6 +75,8 @@ pub struct CorrelationEngine {
     invariant_enforcer: Arc<parking_lot::RwLock<InvariantEnforcer>>,
     /// Configuration
     config: EngineConfig,
+    /// Detection enrichers (applied in order to every emitted detection)
+    enrichers: Vec<Arc<dyn DetectionEnricher>>,
 }
 
 impl CorrelationEngine {
@@ -115,9 +118,16 @@ impl CorrelationEngine {
             scheduler,
             invariant_enforcer,
             config,
+            enrichers: Vec::new(),
         }
     }
 
+    /// Add a detection enricher (e.g. asset criticality tags)
+    pub fn with_enricher(mut self, enricher: Arc<dyn DetectionEnricher>) -> Self {
+        self.enrichers.push(enricher);
+        self
+    }
+
     /// Process validated event
     pub fn process_event(
         &self,
@@ -237,18 +247,22 @@ impl CorrelationEngine {
                 // Create detection result
                 let metadata = DetectionMetadata {
                     engine_version: "1.0.0".to_string(),
-                    rule_id: None,
+                    rule_id: Some(inference.rule_id.clone()),
                     signal_count: signals.len(),
                     stage_transition_count: entity_state.transition_history.len() + 1,
                 };
 
-                let detection = DetectionResult::new(
+                let mut detection = DetectionResult::new(
                     event.entity_id.clone(),
                     inference.stage,
                     inference_confidence, // Use inference confidence for detection
                     explainability,
                     metadata,
                 );
+                detection.attack = inference.attack.clone();
+                for enricher in &self.enrichers {
+                    enricher.enrich(&mut detection);
+                }
 
                 return Ok(Some(detection));
             }
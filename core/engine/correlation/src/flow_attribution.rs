@@ -0,0 +1,315 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_core/correlation/src/flow_attribution.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details: DPI flow attribution - join DPI probe flows to the endpoint sockets that produced them, resolving each flow end to a container entity or, for host activity, the agent's host
+
+/*
+ * Flow Attribution
+ *
+ * Linux agent network events carry the socket's endpoints, its network namespace and (for
+ * processes in containers) the container context. A DPI flow src:sport -> dst:dport is joined to
+ * sockets observed within the time window:
+ *   source side       socket remote == dst:dport, and local port == sport when the agent knows it
+ *   destination side  socket remote == src:sport (accepted connection)
+ * A match on the full tuple (local port known) wins over a remote-only match. Remote-only
+ * matches are what survive NAT (bridge-networked containers egress with the node's address and
+ * a rewritten source port), so they are accepted only when every candidate socket belongs to the
+ * same entity; otherwise that side of the flow stays unattributed.
+ *
+ * A socket with container context attributes to the container (entity_type "container", key =
+ * container id), including containers on the host network namespace; other sockets attribute to
+ * the agent's host (entity_type "agent", key = agent id).
+ */
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+
+const FULL_TUPLE_CONFIDENCE: f64 = 0.95;
+const REMOTE_ONLY_CONFIDENCE: f64 = 0.6;
+
+/// Container context of an endpoint socket
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContainerRef {
+    pub container_id: String,
+    pub image: Option<String>,
+    pub pod_name: Option<String>,
+    pub pod_namespace: Option<String>,
+}
+
+/// One connection observed by an endpoint agent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EndpointSocket {
+    pub agent_id: String,
+    pub host_id: String,
+    pub pid: Option<u32>,
+    pub container: Option<ContainerRef>,
+    pub netns_inode: Option<u64>,
+    pub host_netns: Option<bool>,
+    pub local_ip: Option<IpAddr>,
+    pub local_port: Option<u16>,
+    pub remote_ip: IpAddr,
+    pub remote_port: u16,
+    pub observed_at: DateTime<Utc>,
+}
+
+/// One DPI probe flow
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DpiFlow {
+    pub flow_id: String,
+    pub src_ip: IpAddr,
+    pub src_port: u16,
+    pub dst_ip: IpAddr,
+    pub dst_port: u16,
+    pub observed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FlowSide {
+    Source,
+    Destination,
+}
+
+/// Entity a flow end is attributed to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "entity_type", rename_all = "lowercase")]
+pub enum AttributedEntity {
+    Container { container: ContainerRef, agent_id: String },
+    Agent { agent_id: String, host_id: String },
+}
+
+impl AttributedEntity {
+    fn of(socket: &EndpointSocket) -> Self {
+        match &socket.container {
+            Some(container) => AttributedEntity::Container { container: container.clone(), agent_id: socket.agent_id.clone() },
+            None => AttributedEntity::Agent { agent_id: socket.agent_id.clone(), host_id: socket.host_id.clone() },
+        }
+    }
+
+    /// entities.entity_type
+    pub fn entity_type(&self) -> &'static str {
+        match self {
+            AttributedEntity::Container { .. } => "container",
+            AttributedEntity::Agent { .. } => "agent",
+        }
+    }
+
+    /// entities.entity_key
+    pub fn entity_key(&self) -> &str {
+        match self {
+            AttributedEntity::Container { container, .. } => &container.container_id,
+            AttributedEntity::Agent { agent_id, .. } => agent_id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FlowAttribution {
+    pub flow_id: String,
+    pub side: FlowSide,
+    pub entity: AttributedEntity,
+    pub netns_inode: Option<u64>,
+    pub pid: Option<u32>,
+    /// Whether the local port matched (full tuple) or only the remote endpoint
+    pub full_tuple: bool,
+    pub confidence: f64,
+}
+
+type RemoteKey = (IpAddr, u16);
+
+/// Recent endpoint sockets indexed by remote endpoint
+pub struct FlowAttributor {
+    window: Duration,
+    max_sockets: usize,
+    by_remote: HashMap<RemoteKey, Vec<EndpointSocket>>,
+    order: VecDeque<(RemoteKey, DateTime<Utc>)>,
+    len: usize,
+}
+
+impl FlowAttributor {
+    /// `window`: how far apart socket and flow observations may be
+    pub fn new(window: Duration, max_sockets: usize) -> Self {
+        Self { window, max_sockets, by_remote: HashMap::new(), order: VecDeque::new(), len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn record(&mut self, socket: EndpointSocket) {
+        if self.len >= self.max_sockets {
+            self.evict_oldest();
+        }
+        let key = (socket.remote_ip, socket.remote_port);
+        self.order.push_back((key, socket.observed_at));
+        self.by_remote.entry(key).or_default().push(socket);
+        self.len += 1;
+    }
+
+    /// Forget sockets observed more than one window before `now`
+    pub fn prune(&mut self, now: DateTime<Utc>) {
+        let cutoff = now - self.window;
+        while self.order.front().is_some_and(|(_, at)| *at < cutoff) {
+            self.evict_oldest();
+        }
+    }
+
+    fn evict_oldest(&mut self) {
+        let Some((key, at)) = self.order.pop_front() else { return };
+        if let Some(sockets) = self.by_remote.get_mut(&key) {
+            if let Some(i) = sockets.iter().position(|s| s.observed_at == at) {
+                sockets.remove(i);
+                self.len -= 1;
+            }
+            if sockets.is_empty() {
+                self.by_remote.remove(&key);
+            }
+        }
+    }
+
+    /// Attribute both ends of `flow` (zero, one or two attributions)
+    pub fn attribute(&self, flow: &DpiFlow) -> Vec<FlowAttribution> {
+        let source = self.attribute_side(flow, FlowSide::Source, (flow.dst_ip, flow.dst_port), flow.src_ip, flow.src_port);
+        let destination = self.attribute_side(flow, FlowSide::Destination, (flow.src_ip, flow.src_port), flow.dst_ip, flow.dst_port);
+        source.into_iter().chain(destination).collect()
+    }
+
+    fn attribute_side(&self, flow: &DpiFlow, side: FlowSide, remote: RemoteKey, local_ip: IpAddr, local_port: u16) -> Option<FlowAttribution> {
+        let candidates: Vec<&EndpointSocket> = self
+            .by_remote
+            .get(&remote)?
+            .iter()
+            .filter(|s| (s.observed_at - flow.observed_at).num_milliseconds().abs() <= self.window.num_milliseconds())
+            .collect();
+
+        let full: Vec<&EndpointSocket> = candidates
+            .iter()
+            .copied()
+            .filter(|s| s.local_port == Some(local_port) && s.local_ip.map_or(true, |ip| ip == local_ip))
+            .collect();
+        let (matched, full_tuple, confidence) = if !full.is_empty() {
+            (full, true, FULL_TUPLE_CONFIDENCE)
+        } else {
+            let remote_only: Vec<&EndpointSocket> = candidates.into_iter().filter(|s| s.local_port.is_none()).collect();
+            (remote_only, false, REMOTE_ONLY_CONFIDENCE)
+        };
+
+        // Closest observation in time, provided every candidate is the same entity
+        let closest = matched
+            .iter()
+            .min_by_key(|s| (s.observed_at - flow.observed_at).num_milliseconds().abs())?;
+        let entity = AttributedEntity::of(closest);
+        if matched.iter().any(|s| AttributedEntity::of(s) != entity) {
+            return None;
+        }
+        Some(FlowAttribution {
+            flow_id: flow.flow_id.clone(),
+            side,
+            entity,
+            netns_inode: closest.netns_inode,
+            pid: closest.pid,
+            full_tuple,
+            confidence,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap() + Duration::seconds(secs)
+    }
+
+    fn container(id: &str) -> ContainerRef {
+        ContainerRef {
+            container_id: id.repeat(64),
+            image: Some("nginx:1.25".to_string()),
+            pod_name: Some("web-7d9f".to_string()),
+            pod_namespace: Some("shop".to_string()),
+        }
+    }
+
+    fn socket(container: Option<ContainerRef>, local_port: Option<u16>, observed_at: DateTime<Utc>) -> EndpointSocket {
+        EndpointSocket {
+            agent_id: "agent-1".to_string(),
+            host_id: "node-1".to_string(),
+            pid: Some(4242),
+            container,
+            netns_inode: Some(4026532500),
+            host_netns: Some(false),
+            local_ip: None,
+            local_port,
+            remote_ip: "203.0.113.7".parse().unwrap(),
+            remote_port: 443,
+            observed_at,
+        }
+    }
+
+    fn flow(src_port: u16, observed_at: DateTime<Utc>) -> DpiFlow {
+        DpiFlow {
+            flow_id: "flow-1".to_string(),
+            src_ip: "10.0.0.20".parse().unwrap(),
+            src_port,
+            dst_ip: "203.0.113.7".parse().unwrap(),
+            dst_port: 443,
+            observed_at,
+        }
+    }
+
+    #[test]
+    fn test_attributes_flow_to_container() {
+        let mut attributor = FlowAttributor::new(Duration::seconds(30), 100);
+        attributor.record(socket(Some(container("a")), None, at(0)));
+
+        let attributions = attributor.attribute(&flow(51000, at(5)));
+        assert_eq!(attributions.len(), 1);
+        let a = &attributions[0];
+        assert_eq!(a.side, FlowSide::Source);
+        assert_eq!(a.entity.entity_type(), "container");
+        assert_eq!(a.entity.entity_key(), "a".repeat(64));
+        assert!(!a.full_tuple);
+        assert_eq!(a.netns_inode, Some(4026532500));
+
+        // Outside the window
+        assert!(attributor.attribute(&flow(51000, at(60))).is_empty());
+    }
+
+    #[test]
+    fn test_full_tuple_wins_and_ambiguity_is_unattributed() {
+        let mut attributor = FlowAttributor::new(Duration::seconds(30), 100);
+        attributor.record(socket(Some(container("a")), None, at(0)));
+        attributor.record(socket(None, None, at(1)));
+
+        // Two entities reach the same remote endpoint: remote-only match is ambiguous
+        assert!(attributor.attribute(&flow(51000, at(2))).is_empty());
+
+        attributor.record(socket(None, Some(51000), at(2)));
+        let attributions = attributor.attribute(&flow(51000, at(3)));
+        assert_eq!(attributions.len(), 1);
+        assert_eq!(attributions[0].entity.entity_type(), "agent");
+        assert_eq!(attributions[0].entity.entity_key(), "agent-1");
+        assert!(attributions[0].full_tuple);
+        assert_eq!(attributions[0].confidence, FULL_TUPLE_CONFIDENCE);
+    }
+
+    #[test]
+    fn test_prune_and_bound() {
+        let mut attributor = FlowAttributor::new(Duration::seconds(30), 2);
+        attributor.record(socket(None, None, at(0)));
+        attributor.record(socket(None, None, at(10)));
+        attributor.record(socket(None, None, at(20)));
+        assert_eq!(attributor.len(), 2);
+        attributor.prune(at(45));
+        assert_eq!(attributor.len(), 1);
+        attributor.prune(at(100));
+        assert!(attributor.is_empty());
+    }
+}
@@ -6,6 +6,7 @@ pub mod engine;
 pub mod entity_state;
 pub mod errors;
 pub mod explainability;
+pub mod flow_attribution;
 pub mod graph;
 pub mod invariants;
 pub mod scheduler;
@@ -26,6 +27,8 @@ pub mod output;
 
 pub use crate::engine::{CorrelationEngine, EngineConfig, EngineStats};
 pub use crate::errors::{CorrelationError, CorrelationResult};
+pub use crate::flow_attribution::{AttributedEntity, DpiFlow, EndpointSocket, FlowAttribution, FlowAttributor};
 pub use crate::input::validated_events::ValidatedEvent;
 pub use crate::output::detection_result::DetectionResult;
 
+pub use crate::output::enrichment::{AssetCatalog, AssetCriticality, AssetTagEnricher, AssetTags, DetectionEnricher};
@@ -0,0 +1,303 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/orchestrator/src/asset_tags.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Asset criticality tags - CSV import into asset_tags and loading of the correlation asset catalog (hostname/IP tags, plus agent ids resolved through the agents table)
+
+/*
+ * Asset Tags
+ *
+ * CSV format (header row required, column order free):
+ *
+ *   hostname,ip,criticality,owner,environment
+ *   db01,10.1.2.3,critical,finance-it,production
+ *
+ * Each row needs a hostname or an ip; criticality is low|medium|high|critical; owner and
+ * environment are optional. Fields may be double-quoted ("a,b" / "say ""hi"""). Hostnames are
+ * stored lower-cased. Importing a row replaces any existing row with the same hostname or ip;
+ * --replace clears the table first. The import is one transaction.
+ */
+
+use std::collections::HashSet;
+use std::net::IpAddr;
+
+use ransomeye_core::{AssetCatalog, AssetCriticality, AssetTags};
+use uuid::Uuid;
+
+use super::db::CoreDb;
+
+const REQUIRED_COLUMNS: &[&str] = &["hostname", "ip", "criticality"];
+
+/// One validated asset_tags row
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssetTagRow {
+    pub hostname: Option<String>,
+    pub ip: Option<IpAddr>,
+    pub tags: AssetTags,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ImportSummary {
+    pub rows: usize,
+    /// Existing rows replaced (same hostname or ip, or all rows with --replace)
+    pub replaced: u64,
+}
+
+/// Split one CSV line into fields (RFC 4180 quoting, no embedded newlines).
+fn split_csv_line(line: &str) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut chars = line.chars().peekable();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    if quoted {
+        return Err("unterminated quoted field".to_string());
+    }
+    fields.push(field);
+    Ok(fields)
+}
+
+fn non_empty(value: Option<&String>) -> Option<String> {
+    value.map(|v| v.trim()).filter(|v| !v.is_empty()).map(str::to_string)
+}
+
+/// Parse and validate an asset tag CSV. Errors name the offending line; duplicate
+/// hostnames/IPs within one file are rejected.
+pub fn parse_csv(raw: &str) -> Result<Vec<AssetTagRow>, String> {
+    let mut lines = raw
+        .lines()
+        .enumerate()
+        .map(|(i, l)| (i + 1, l.trim_end_matches('\r')))
+        .filter(|(_, l)| !l.trim().is_empty() && !l.trim_start().starts_with('#'));
+
+    let (_, header) = lines.next().ok_or("Asset tag CSV is empty")?;
+    let header: Vec<String> = split_csv_line(header)?
+        .into_iter()
+        .map(|h| h.trim().to_ascii_lowercase())
+        .collect();
+    let column = |name: &str| header.iter().position(|h| h == name);
+    for required in REQUIRED_COLUMNS {
+        if column(required).is_none() {
+            return Err(format!("Asset tag CSV header is missing column '{}'", required));
+        }
+    }
+    let (hostname_col, ip_col, criticality_col) = (column("hostname"), column("ip"), column("criticality"));
+    let (owner_col, environment_col) = (column("owner"), column("environment"));
+
+    let mut rows = Vec::new();
+    let mut seen_hostnames = HashSet::new();
+    let mut seen_ips = HashSet::new();
+    for (line_no, line) in lines {
+        let fields = split_csv_line(line).map_err(|e| format!("line {}: {}", line_no, e))?;
+        if fields.len() != header.len() {
+            return Err(format!(
+                "line {}: expected {} fields, found {}",
+                line_no,
+                header.len(),
+                fields.len()
+            ));
+        }
+        let field = |col: Option<usize>| non_empty(col.and_then(|c| fields.get(c)));
+
+        let hostname = field(hostname_col).map(|h| h.to_ascii_lowercase());
+        let ip = match field(ip_col) {
+            Some(v) => Some(
+                v.parse::<IpAddr>()
+                    .map_err(|_| format!("line {}: invalid ip '{}'", line_no, v))?,
+            ),
+            None => None,
+        };
+        if hostname.is_none() && ip.is_none() {
+            return Err(format!("line {}: hostname or ip is required", line_no));
+        }
+        let criticality_raw = field(criticality_col).unwrap_or_default();
+        let criticality = AssetCriticality::parse(&criticality_raw).ok_or_else(|| {
+            format!(
+                "line {}: invalid criticality '{}' (expected low|medium|high|critical)",
+                line_no, criticality_raw
+            )
+        })?;
+        if let Some(h) = &hostname {
+            if !seen_hostnames.insert(h.clone()) {
+                return Err(format!("line {}: duplicate hostname '{}'", line_no, h));
+            }
+        }
+        if let Some(ip) = ip {
+            if !seen_ips.insert(ip) {
+                return Err(format!("line {}: duplicate ip '{}'", line_no, ip));
+            }
+        }
+
+        rows.push(AssetTagRow {
+            hostname,
+            ip,
+            tags: AssetTags {
+                criticality,
+                owner: field(owner_col),
+                environment: field(environment_col),
+            },
+        });
+    }
+    Ok(rows)
+}
+
+/// Import rows into asset_tags in one transaction.
+pub async fn import(db: &CoreDb, rows: &[AssetTagRow], source: &str, replace_all: bool) -> Result<ImportSummary, String> {
+    let client = db.client();
+    client
+        .batch_execute("BEGIN")
+        .await
+        .map_err(|e| format!("Failed to begin asset tag import: {e}"))?;
+    let result = async {
+        let mut summary = ImportSummary { rows: rows.len(), replaced: 0 };
+        if replace_all {
+            summary.replaced += client
+                .execute("DELETE FROM asset_tags", &[])
+                .await
+                .map_err(|e| format!("Failed to clear asset_tags: {e}"))?;
+        }
+        for row in rows {
+            summary.replaced += client
+                .execute(
+                    "DELETE FROM asset_tags WHERE hostname = $1 OR ip = $2",
+                    &[&row.hostname, &row.ip],
+                )
+                .await
+                .map_err(|e| format!("Failed to replace asset_tags row: {e}"))?;
+            client
+                .execute(
+                    r#"
+                    INSERT INTO asset_tags (asset_tag_id, hostname, ip, criticality, owner, environment, source)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7)
+                    "#,
+                    &[
+                        &Uuid::new_v4(),
+                        &row.hostname,
+                        &row.ip,
+                        &row.tags.criticality.as_str(),
+                        &row.tags.owner,
+                        &row.tags.environment,
+                        &source,
+                    ],
+                )
+                .await
+                .map_err(|e| format!("Failed to insert asset_tags row: {e}"))?;
+        }
+        Ok::<ImportSummary, String>(summary)
+    }
+    .await;
+
+    match result {
+        Ok(summary) => client
+            .batch_execute("COMMIT")
+            .await
+            .map(|_| summary)
+            .map_err(|e| format!("Failed to commit asset tag import: {e}")),
+        Err(e) => {
+            let _ = client.batch_execute("ROLLBACK").await;
+            Err(e)
+        }
+    }
+}
+
+/// Load asset_tags into a catalog. Agents whose recorded hostname/FQDN is tagged are also
+/// indexed by agent id, since correlation entities are agent ids.
+pub async fn load_catalog(db: &CoreDb) -> Result<AssetCatalog, String> {
+    let rows = db
+        .client()
+        .query(
+            "SELECT hostname, ip, criticality, owner, environment FROM asset_tags",
+            &[],
+        )
+        .await
+        .map_err(|e| format!("FAIL-CLOSED: Cannot read asset_tags: {e}"))?;
+
+    let mut catalog = AssetCatalog::new();
+    for row in rows {
+        let criticality: String = row.get(2);
+        let tags = AssetTags {
+            criticality: AssetCriticality::parse(&criticality)
+                .ok_or_else(|| format!("FAIL-CLOSED: asset_tags has invalid criticality '{}'", criticality))?,
+            owner: row.get(3),
+            environment: row.get(4),
+        };
+        if let Some(hostname) = row.get::<_, Option<String>>(0) {
+            catalog.insert_hostname(&hostname, tags.clone());
+        }
+        if let Some(ip) = row.get::<_, Option<IpAddr>>(1) {
+            catalog.insert_ip(ip, tags);
+        }
+    }
+    if catalog.is_empty() {
+        return Ok(catalog);
+    }
+
+    let agents = db
+        .client()
+        .query("SELECT agent_id, host_hostname, host_fqdn FROM agents", &[])
+        .await
+        .map_err(|e| format!("FAIL-CLOSED: Cannot read agents for asset tags: {e}"))?;
+    for agent in agents {
+        let agent_id: Uuid = agent.get(0);
+        let names: [Option<String>; 2] = [agent.get(2), agent.get(1)];
+        let tags = names
+            .iter()
+            .flatten()
+            .find_map(|name| catalog.lookup(name).cloned());
+        if let Some(tags) = tags {
+            catalog.insert_entity(&agent_id.to_string(), tags);
+        }
+    }
+    Ok(catalog)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_quoted_fields_and_any_column_order() {
+        let raw = "# exported from CMDB\n\
+                   criticality,ip,hostname,owner,environment\r\n\
+                   critical,10.1.2.3,DB01,\"Finance, IT\",production\n\
+                   \n\
+                   Low,,kiosk-7,,\n\
+                   high,2001:db8::1,,\"say \"\"hi\"\"\",\n";
+        let rows = parse_csv(raw).unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].hostname.as_deref(), Some("db01"));
+        assert_eq!(rows[0].ip, Some("10.1.2.3".parse().unwrap()));
+        assert_eq!(rows[0].tags.criticality, AssetCriticality::Critical);
+        assert_eq!(rows[0].tags.owner.as_deref(), Some("Finance, IT"));
+        assert_eq!(rows[1].ip, None);
+        assert_eq!(rows[1].tags.owner, None);
+        assert_eq!(rows[1].tags.criticality, AssetCriticality::Low);
+        assert_eq!(rows[2].hostname, None);
+        assert_eq!(rows[2].tags.owner.as_deref(), Some("say \"hi\""));
+    }
+
+    #[test]
+    fn rejects_invalid_rows() {
+        let header = "hostname,ip,criticality\n";
+        assert!(parse_csv("hostname,criticality\nweb01,low\n").unwrap_err().contains("'ip'"));
+        assert!(parse_csv(&format!("{header},,high\n")).unwrap_err().contains("hostname or ip"));
+        assert!(parse_csv(&format!("{header}web01,,urgent\n")).unwrap_err().contains("criticality"));
+        assert!(parse_csv(&format!("{header}web01,10.0.0.300,low\n")).unwrap_err().contains("invalid ip"));
+        assert!(parse_csv(&format!("{header}web01,,low,extra\n")).unwrap_err().contains("line 2"));
+        assert!(parse_csv(&format!("{header}Web01,,low\nweb01,,high\n")).unwrap_err().contains("duplicate hostname"));
+        assert!(parse_csv(&format!("{header}\"web01,,low\n")).unwrap_err().contains("unterminated"));
+        assert!(parse_csv("").is_err());
+    }
+}
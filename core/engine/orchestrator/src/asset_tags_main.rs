@@ -0,0 +1,102 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/orchestrator/src/asset_tags_main.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Standalone asset tag import binary - validates a CSV of hostname/IP criticality, owner and environment tags and loads it into asset_tags.
+
+use std::path::Path;
+use std::process;
+
+use tracing::{error, info};
+
+#[path = "lib.rs"]
+mod orchestrator;
+
+use orchestrator::asset_tags;
+use orchestrator::db::{CoreDb, DbConfig};
+
+fn usage_and_exit() -> ! {
+    eprintln!("RansomEye Asset Tag Import");
+    eprintln!("");
+    eprintln!("USAGE:");
+    eprintln!("  ransomeye_asset_tags --csv <assets.csv> [--replace] [--dry-run]");
+    eprintln!("");
+    eprintln!("NOTES:");
+    eprintln!("  - CSV header: hostname,ip,criticality[,owner][,environment] (any order)");
+    eprintln!("  - criticality: low|medium|high|critical; each row needs a hostname or an ip");
+    eprintln!("  - Rows replace existing tags with the same hostname or ip; --replace clears all tags first");
+    eprintln!("  - --dry-run validates the CSV without connecting to the database");
+    eprintln!("  - DB env vars are required: DB_HOST, DB_PORT, DB_NAME, DB_USER, DB_PASS");
+    process::exit(2);
+}
+
+fn arg_value(name: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter()
+        .position(|a| a == name)
+        .and_then(|i| args.get(i + 1).cloned())
+}
+
+#[tokio::main]
+async fn main() {
+    orchestrator::BUILD_INFO.handle_version_flag("ransomeye_asset_tags");
+    tracing_subscriber::fmt().with_writer(std::io::stderr).init();
+
+    if std::env::args().any(|a| a == "--help" || a == "-h") {
+        usage_and_exit();
+    }
+    let Some(csv_path) = arg_value("--csv") else {
+        usage_and_exit();
+    };
+    let replace_all = std::env::args().any(|a| a == "--replace");
+    let dry_run = std::env::args().any(|a| a == "--dry-run");
+
+    let raw = match std::fs::read_to_string(&csv_path) {
+        Ok(s) => s,
+        Err(e) => {
+            error!("Failed to read asset tag CSV at {}: {}", csv_path, e);
+            process::exit(1);
+        }
+    };
+    let rows = match asset_tags::parse_csv(&raw) {
+        Ok(rows) => rows,
+        Err(e) => {
+            error!("Invalid asset tag CSV {}: {}", csv_path, e);
+            process::exit(1);
+        }
+    };
+    if dry_run {
+        info!("[ASSET-TAGS] dry run: {} valid rows in {}", rows.len(), csv_path);
+        process::exit(0);
+    }
+
+    let cfg = match DbConfig::from_env_strict() {
+        Ok(c) => c,
+        Err(e) => {
+            error!("{e}");
+            process::exit(1);
+        }
+    };
+    let db = match CoreDb::connect_strict(&cfg).await {
+        Ok(db) => db,
+        Err(e) => {
+            error!("FAIL-CLOSED: DB connect failed: {e}");
+            process::exit(1);
+        }
+    };
+
+    let source = Path::new(&csv_path)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| csv_path.clone());
+    match asset_tags::import(&db, &rows, &source, replace_all).await {
+        Ok(summary) => {
+            info!(
+                "[ASSET-TAGS] imported={} replaced={} source={}",
+                summary.rows, summary.replaced, source
+            );
+        }
+        Err(e) => {
+            error!("FAIL-CLOSED: {e}");
+            process::exit(1);
+        }
+    }
+}
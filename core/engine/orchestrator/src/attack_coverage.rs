@@ -0,0 +1,290 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/orchestrator/src/attack_coverage.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: MITRE ATT&CK coverage - which catalog techniques the current correlation rules and built-in ingest detectors are tagged with, and which the deployed sensor types can observe
+
+/*
+ * ATT&CK Coverage
+ *
+ * Two questions per technique of the catalog (ingest::attack::TECHNIQUES):
+ *
+ *   detection   is any current rule tagged with it: the correlation kill-chain rules and the
+ *               detectors built into ingest
+ *   visibility  does a deployed sensor type produce one of the data sources that reveal it
+ *
+ *   detected     a rule and a sensor
+ *   rule_only    a rule, but no deployed sensor sees its data
+ *   observable   data arrives, but no rule is tagged with it
+ *   blind        neither
+ *
+ * Techniques tagged by a rule but missing from the catalog are listed as rule_only without a
+ * name; their data sources are unknown. Rules without any tag are listed separately.
+ */
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+
+use chrono::{DateTime, Utc};
+use ingest::attack::{self, AttackTag, DataSource, BUILTIN_DETECTORS, TACTICS};
+use ransomeye_core::kill_chain::rules::KillChainRuleEngine;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleSource {
+    /// Correlation engine kill-chain rule
+    Correlation,
+    /// Detector built into ingest
+    Ingest,
+}
+
+/// A current rule and its ATT&CK tags
+#[derive(Debug, Clone, Serialize)]
+pub struct TaggedRule {
+    pub source: RuleSource,
+    /// Correlation rule id, or `<detection_engine>/<detection_name>`
+    pub rule: String,
+    pub attack: Vec<AttackTag>,
+}
+
+impl TaggedRule {
+    fn label(&self) -> String {
+        match self.source {
+            RuleSource::Correlation => format!("correlation:{}", self.rule),
+            RuleSource::Ingest => format!("ingest:{}", self.rule),
+        }
+    }
+}
+
+/// Rules shipped with this build: the correlation engine's default rules and ingest's detectors
+pub fn current_rules() -> Vec<TaggedRule> {
+    let engine = KillChainRuleEngine::new();
+    let correlation = engine.rules().iter().map(|r| TaggedRule {
+        source: RuleSource::Correlation,
+        rule: r.id.clone(),
+        attack: r.attack.clone(),
+    });
+    let detectors = BUILTIN_DETECTORS.iter().map(|d| TaggedRule {
+        source: RuleSource::Ingest,
+        rule: format!("{}/{}", d.detection_engine, d.detection_name),
+        attack: attack::for_detector(d.detection_engine, d.detection_name).into_iter().collect(),
+    });
+    correlation.chain(detectors).collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TechniqueStatus {
+    Detected,
+    RuleOnly,
+    Observable,
+    Blind,
+}
+
+impl TechniqueStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TechniqueStatus::Detected => "detected",
+            TechniqueStatus::RuleOnly => "rule_only",
+            TechniqueStatus::Observable => "observable",
+            TechniqueStatus::Blind => "blind",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TechniqueCoverage {
+    pub technique_id: String,
+    /// None for techniques outside the catalog
+    pub name: Option<String>,
+    pub tactic: String,
+    pub status: TechniqueStatus,
+    /// Data sources that reveal the technique
+    pub data_sources: Vec<DataSource>,
+    /// Rules tagged with it
+    pub rules: Vec<String>,
+    /// Deployed sensor types that produce one of its data sources
+    pub sensors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AttackCoverageReport {
+    pub generated_at: DateTime<Utc>,
+    /// Sensor types the visibility is computed for
+    pub sensors: Vec<String>,
+    pub detected: usize,
+    pub rule_only: usize,
+    pub observable: usize,
+    pub blind: usize,
+    /// In tactic order, then by technique id
+    pub techniques: Vec<TechniqueCoverage>,
+    pub untagged_rules: Vec<String>,
+}
+
+fn tactic_order(slug: &str) -> usize {
+    TACTICS.iter().position(|t| t.slug == slug).unwrap_or(TACTICS.len())
+}
+
+/// Coverage of the catalog by `rules` and the `sensors` (agent_type values) deployed (pure).
+pub fn analyze(rules: &[TaggedRule], sensors: &BTreeSet<String>, now: DateTime<Utc>) -> AttackCoverageReport {
+    let mut tagged: BTreeMap<String, (String, Vec<String>)> = BTreeMap::new();
+    let mut untagged_rules = Vec::new();
+    for rule in rules {
+        if rule.attack.is_empty() {
+            untagged_rules.push(rule.label());
+        }
+        for tag in &rule.attack {
+            let entry = tagged.entry(tag.technique_id.clone()).or_insert_with(|| (tag.tactic.clone(), Vec::new()));
+            if !entry.1.contains(&rule.label()) {
+                entry.1.push(rule.label());
+            }
+        }
+    }
+    let sensor_sources: Vec<(&String, BTreeSet<DataSource>)> =
+        sensors.iter().map(|s| (s, attack::sensor_data_sources(s))).collect();
+
+    let mut techniques: Vec<TechniqueCoverage> = attack::TECHNIQUES
+        .iter()
+        .map(|t| {
+            let rules = tagged.remove(t.id).map(|(_, rules)| rules).unwrap_or_default();
+            let sensors: Vec<String> = sensor_sources
+                .iter()
+                .filter(|(_, sources)| t.data_sources.iter().any(|d| sources.contains(d)))
+                .map(|(s, _)| s.to_string())
+                .collect();
+            let status = match (rules.is_empty(), sensors.is_empty()) {
+                (false, false) => TechniqueStatus::Detected,
+                (false, true) => TechniqueStatus::RuleOnly,
+                (true, false) => TechniqueStatus::Observable,
+                (true, true) => TechniqueStatus::Blind,
+            };
+            TechniqueCoverage {
+                technique_id: t.id.to_string(),
+                name: Some(t.name.to_string()),
+                tactic: t.tactic.to_string(),
+                status,
+                data_sources: t.data_sources.to_vec(),
+                rules,
+                sensors,
+            }
+        })
+        .collect();
+    // Tagged but not in the catalog: nothing is known about the data it needs
+    techniques.extend(tagged.into_iter().map(|(technique_id, (tactic, rules))| TechniqueCoverage {
+        technique_id,
+        name: None,
+        tactic,
+        status: TechniqueStatus::RuleOnly,
+        data_sources: Vec::new(),
+        rules,
+        sensors: Vec::new(),
+    }));
+    techniques.sort_by(|a, b| (tactic_order(&a.tactic), &a.technique_id).cmp(&(tactic_order(&b.tactic), &b.technique_id)));
+
+    let count = |status| techniques.iter().filter(|t| t.status == status).count();
+    AttackCoverageReport {
+        generated_at: now,
+        sensors: sensors.iter().cloned().collect(),
+        detected: count(TechniqueStatus::Detected),
+        rule_only: count(TechniqueStatus::RuleOnly),
+        observable: count(TechniqueStatus::Observable),
+        blind: count(TechniqueStatus::Blind),
+        techniques,
+        untagged_rules,
+    }
+}
+
+impl AttackCoverageReport {
+    pub fn render_text(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "RansomEye ATT&CK Coverage ({})", self.generated_at.to_rfc3339());
+        let sensors = if self.sensors.is_empty() { "none".to_string() } else { self.sensors.join(", ") };
+        let _ = writeln!(out, "Sensors: {}", sensors);
+        let _ = writeln!(
+            out,
+            "Techniques: {} | detected: {} | rule only: {} | observable: {} | blind: {}",
+            self.techniques.len(),
+            self.detected,
+            self.rule_only,
+            self.observable,
+            self.blind
+        );
+        let mut tactic = "";
+        for t in &self.techniques {
+            if t.tactic != tactic {
+                tactic = &t.tactic;
+                let _ = writeln!(out, "\n{}:", attack::tactic(tactic).map(|t| t.name).unwrap_or(tactic));
+            }
+            let _ = writeln!(
+                out,
+                "  {:<10} {:<11} {:<54} rules: {}",
+                t.technique_id,
+                t.status.as_str(),
+                t.name.as_deref().unwrap_or("(not in catalog)"),
+                if t.rules.is_empty() { "-".to_string() } else { t.rules.join(", ") }
+            );
+        }
+        let _ = writeln!(out, "\nUntagged rules ({}):", self.untagged_rules.len());
+        for r in &self.untagged_rules {
+            let _ = writeln!(out, "  {}", r);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(source: RuleSource, name: &str, tags: &[(&str, &str)]) -> TaggedRule {
+        TaggedRule {
+            source,
+            rule: name.to_string(),
+            attack: tags.iter().map(|(t, tactic)| AttackTag::new(t, tactic).unwrap()).collect(),
+        }
+    }
+
+    fn status(report: &AttackCoverageReport, technique_id: &str) -> TechniqueStatus {
+        report.techniques.iter().find(|t| t.technique_id == technique_id).unwrap().status
+    }
+
+    #[test]
+    fn classifies_techniques_by_rules_and_sensors() {
+        let rules = vec![
+            rule(RuleSource::Correlation, "encryption_execution_1", &[("T1486", "impact")]),
+            rule(RuleSource::Correlation, "smb_spread", &[("T1021.002", "lateral-movement"), ("T1570", "lateral-movement")]),
+            rule(RuleSource::Ingest, "ingest_rate_budget/ingest_rate_budget_exceeded", &[]),
+        ];
+        let sensors: BTreeSet<String> = ["linux_agent".to_string()].into();
+        let report = analyze(&rules, &sensors, Utc::now());
+
+        // File activity from the Linux agent and a rule for it
+        assert_eq!(status(&report, "T1486"), TechniqueStatus::Detected);
+        // Linux network events reveal SMB spread; no rule for service discovery
+        assert_eq!(status(&report, "T1021.002"), TechniqueStatus::Detected);
+        assert_eq!(status(&report, "T1046"), TechniqueStatus::Observable);
+        // Scheduled tasks need the Windows agent
+        assert_eq!(status(&report, "T1053.005"), TechniqueStatus::Blind);
+        let outside = report.techniques.iter().find(|t| t.technique_id == "T1570").unwrap();
+        assert_eq!((outside.status, outside.name.as_deref()), (TechniqueStatus::RuleOnly, None));
+        assert_eq!(report.untagged_rules, vec!["ingest:ingest_rate_budget/ingest_rate_budget_exceeded"]);
+        assert_eq!(report.detected + report.rule_only + report.observable + report.blind, report.techniques.len());
+        // Tactic order: lateral movement before impact
+        let position = |id: &str| report.techniques.iter().position(|t| t.technique_id == id).unwrap();
+        assert!(position("T1021.002") < position("T1486"));
+    }
+
+    #[test]
+    fn windows_agent_sees_service_and_task_techniques() {
+        let sensors: BTreeSet<String> = ["windows_agent".to_string()].into();
+        let report = analyze(&current_rules(), &sensors, Utc::now());
+        assert_eq!(status(&report, "T1543.003"), TechniqueStatus::Observable);
+        assert_eq!(status(&report, "T1070.001"), TechniqueStatus::Observable);
+        assert_eq!(status(&report, "T1486"), TechniqueStatus::Detected);
+        // Only the DPI probe sees flows
+        assert_eq!(status(&report, "T1071"), TechniqueStatus::Blind);
+
+        let none = analyze(&current_rules(), &BTreeSet::new(), Utc::now());
+        assert_eq!(status(&none, "T1486"), TechniqueStatus::RuleOnly);
+        assert!(none.untagged_rules.contains(&"correlation:initial_access_1".to_string()));
+    }
+}
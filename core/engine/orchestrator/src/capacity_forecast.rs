@@ -0,0 +1,484 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/orchestrator/src/capacity_forecast.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Database capacity forecast - periodic table size samples (db_size_samples), growth rates per table and a days-until-full projection that honours retention policies, with the alert level reported by the status API
+
+/*
+ * Database Capacity Forecast
+ *
+ * Every RANSOMEYE_DB_CAPACITY_SAMPLE_SECS the orchestrator records the database size and the size
+ * of every table in the ransomeye and public schemas (one db_size_samples row). The forecast fits
+ * a least-squares growth rate per table over the last RANSOMEYE_DB_CAPACITY_LOOKBACK_DAYS and
+ * projects the database forward:
+ *   - a table with an enabled retention policy grows until it holds retention_days of data, so it
+ *     levels off at max(current size, daily growth x retention_days)
+ *   - any other table keeps growing at its rate; a shrinking table is held at its size (Postgres
+ *     reuses freed pages but does not hand them back to the disk)
+ *   - the rest of the database (system catalogs, sequences) stays as it is
+ * days_until_full is when the projection reaches RANSOMEYE_DB_CAPACITY_GB. The level is `low`
+ * below RANSOMEYE_DB_CAPACITY_ALERT_DAYS, `ok` otherwise, and `unknown` without a configured
+ * capacity or with less than MIN_HISTORY_HOURS of samples.
+ */
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use tokio_postgres::types::Json;
+use uuid::Uuid;
+
+use super::db::CoreDb;
+use super::retention_enforcer::QualifiedTable;
+
+/// Shortest sample history a growth rate is fitted on
+pub const MIN_HISTORY_HOURS: i64 = 6;
+/// Samples older than this are deleted when a new one is taken
+pub const SAMPLE_KEEP_DAYS: i64 = 90;
+/// A projection further out than this is reported as never full
+pub const MAX_FORECAST_DAYS: f64 = 3650.0;
+/// Tables listed in the forecast, fastest growing first
+pub const MAX_REPORTED_TABLES: usize = 10;
+
+const GIB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapacityConfig {
+    /// Seconds between samples (RANSOMEYE_DB_CAPACITY_SAMPLE_SECS); 0 disables the job
+    pub sample_interval_secs: u64,
+    /// Space available to the database (RANSOMEYE_DB_CAPACITY_GB); None = sample only
+    pub capacity_bytes: Option<i64>,
+    pub lookback_days: i64,
+    pub alert_days: f64,
+}
+
+impl CapacityConfig {
+    pub fn from_env() -> Result<Self, String> {
+        let sample_interval_secs = match std::env::var("RANSOMEYE_DB_CAPACITY_SAMPLE_SECS") {
+            Ok(v) => v
+                .parse::<u64>()
+                .map_err(|_| format!("Invalid RANSOMEYE_DB_CAPACITY_SAMPLE_SECS='{}' (expected integer >= 0)", v))?,
+            Err(_) => 3600,
+        };
+        let capacity_bytes = match std::env::var("RANSOMEYE_DB_CAPACITY_GB") {
+            Ok(v) => Some(
+                v.parse::<f64>()
+                    .ok()
+                    .filter(|gb| gb.is_finite() && *gb > 0.0)
+                    .map(|gb| (gb * GIB) as i64)
+                    .ok_or_else(|| format!("Invalid RANSOMEYE_DB_CAPACITY_GB='{}' (expected a number > 0)", v))?,
+            ),
+            Err(_) => None,
+        };
+        let lookback_days = match std::env::var("RANSOMEYE_DB_CAPACITY_LOOKBACK_DAYS") {
+            Ok(v) => v
+                .parse::<i64>()
+                .ok()
+                .filter(|d| (1..=SAMPLE_KEEP_DAYS).contains(d))
+                .ok_or_else(|| format!("Invalid RANSOMEYE_DB_CAPACITY_LOOKBACK_DAYS='{}' (1-{})", v, SAMPLE_KEEP_DAYS))?,
+            Err(_) => 7,
+        };
+        let alert_days = match std::env::var("RANSOMEYE_DB_CAPACITY_ALERT_DAYS") {
+            Ok(v) => v
+                .parse::<f64>()
+                .ok()
+                .filter(|d| d.is_finite() && *d > 0.0)
+                .ok_or_else(|| format!("Invalid RANSOMEYE_DB_CAPACITY_ALERT_DAYS='{}' (expected a number > 0)", v))?,
+            Err(_) => 30.0,
+        };
+        Ok(Self { sample_interval_secs, capacity_bytes, lookback_days, alert_days })
+    }
+}
+
+/// One db_size_samples row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SizeSample {
+    pub sampled_at: DateTime<Utc>,
+    pub database_bytes: i64,
+    /// schema.table -> pg_total_relation_size (heap, indexes and TOAST)
+    pub tables: BTreeMap<String, i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CapacityLevel {
+    Ok,
+    Low,
+    Unknown,
+}
+
+impl CapacityLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CapacityLevel::Ok => "ok",
+            CapacityLevel::Low => "low",
+            CapacityLevel::Unknown => "unknown",
+        }
+    }
+}
+
+/// Projection of one table.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TableForecast {
+    pub table: String,
+    pub current_bytes: i64,
+    /// Fitted over the lookback (negative when the table shrank)
+    pub growth_bytes_per_day: f64,
+    pub retention_days: Option<i64>,
+    /// Size the table levels off at under its retention policy (None: keeps growing)
+    pub ceiling_bytes: Option<i64>,
+}
+
+impl TableForecast {
+    /// Growth used by the projection: shrinking tables do not free disk space.
+    fn projected_rate(&self) -> f64 {
+        self.growth_bytes_per_day.max(0.0)
+    }
+}
+
+/// Forecast as reported by GET /v1/status.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CapacityForecast {
+    pub computed_at: DateTime<Utc>,
+    pub level: CapacityLevel,
+    /// Why the level is unknown
+    pub detail: Option<String>,
+    pub database_bytes: i64,
+    pub capacity_bytes: Option<i64>,
+    pub used_percent: Option<f64>,
+    /// Current growth of the database, tables still filling towards their ceiling
+    pub growth_bytes_per_day: f64,
+    pub days_until_full: Option<f64>,
+    pub projected_full_at: Option<DateTime<Utc>>,
+    pub alert_days: f64,
+    pub samples: usize,
+    pub history_hours: f64,
+    /// Fastest growing tables (at most MAX_REPORTED_TABLES)
+    pub tables: Vec<TableForecast>,
+}
+
+/// Least-squares slope of bytes over time, in bytes per day. None for fewer than two samples or
+/// a history shorter than MIN_HISTORY_HOURS.
+pub fn growth_per_day(points: &[(DateTime<Utc>, i64)]) -> Option<f64> {
+    let first = points.first()?.0;
+    let last = points.last()?.0;
+    if points.len() < 2 || last - first < Duration::hours(MIN_HISTORY_HOURS) {
+        return None;
+    }
+    let xs: Vec<f64> = points.iter().map(|(at, _)| (*at - first).num_seconds() as f64 / 86_400.0).collect();
+    let n = points.len() as f64;
+    let mean_x = xs.iter().sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, b)| *b as f64).sum::<f64>() / n;
+    let (mut cov, mut var) = (0.0, 0.0);
+    for (x, (_, bytes)) in xs.iter().zip(points) {
+        cov += (x - mean_x) * (*bytes as f64 - mean_y);
+        var += (x - mean_x) * (x - mean_x);
+    }
+    (var > 0.0).then(|| cov / var)
+}
+
+/// Per table projections from the samples (oldest first) and the enabled retention policies
+/// (schema.table -> retention_days). Tables missing from the latest sample were dropped and are
+/// left out; tables that appeared recently are fitted on the samples they have.
+pub fn table_forecasts(samples: &[SizeSample], retention: &HashMap<String, i64>) -> Vec<TableForecast> {
+    let Some(latest) = samples.last() else {
+        return Vec::new();
+    };
+    latest
+        .tables
+        .iter()
+        .map(|(table, current)| {
+            let points: Vec<(DateTime<Utc>, i64)> = samples
+                .iter()
+                .filter_map(|s| s.tables.get(table).map(|bytes| (s.sampled_at, *bytes)))
+                .collect();
+            let growth = growth_per_day(&points).unwrap_or(0.0);
+            let retention_days = retention.get(table).copied();
+            let ceiling_bytes = retention_days.map(|days| (*current).max((growth.max(0.0) * days as f64) as i64));
+            TableForecast {
+                table: table.clone(),
+                current_bytes: *current,
+                growth_bytes_per_day: growth,
+                retention_days,
+                ceiling_bytes,
+            }
+        })
+        .collect()
+}
+
+/// Days until the projected database size reaches `capacity_bytes`; None when it never does within
+/// MAX_FORECAST_DAYS. The projection is piecewise linear: each retained table stops adding once it
+/// reaches its ceiling.
+pub fn days_until_full(database_bytes: i64, tables: &[TableForecast], capacity_bytes: i64) -> Option<f64> {
+    let capacity = capacity_bytes as f64;
+    let mut total = database_bytes as f64;
+    if total >= capacity {
+        return Some(0.0);
+    }
+    let mut rate: f64 = tables.iter().map(TableForecast::projected_rate).sum();
+    // When each retained, growing table levels off, and the rate it stops adding
+    let mut saturations: Vec<(f64, f64)> = tables
+        .iter()
+        .filter(|t| t.projected_rate() > 0.0)
+        .filter_map(|t| {
+            let room = (t.ceiling_bytes? - t.current_bytes).max(0) as f64;
+            Some((room / t.projected_rate(), t.projected_rate()))
+        })
+        .collect();
+    saturations.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut day = 0.0;
+    for (at, stops) in saturations {
+        if rate > 0.0 && total + rate * (at - day) >= capacity {
+            break;
+        }
+        total += rate * (at - day);
+        day = at;
+        rate -= stops;
+    }
+    if rate <= f64::EPSILON {
+        return None;
+    }
+    let full = day + (capacity - total) / rate;
+    (full <= MAX_FORECAST_DAYS).then_some(full)
+}
+
+/// Forecast from the samples of the lookback (oldest first).
+pub fn forecast(
+    samples: &[SizeSample],
+    retention: &HashMap<String, i64>,
+    cfg: &CapacityConfig,
+    now: DateTime<Utc>,
+) -> CapacityForecast {
+    let latest = samples.last();
+    let database_bytes = latest.map(|s| s.database_bytes).unwrap_or(0);
+    let history_hours = match (samples.first(), latest) {
+        (Some(first), Some(last)) => (last.sampled_at - first.sampled_at).num_seconds() as f64 / 3600.0,
+        _ => 0.0,
+    };
+    let mut tables = table_forecasts(samples, retention);
+    // Tables at their ceiling no longer add to the database
+    let growth_bytes_per_day = tables
+        .iter()
+        .filter(|t| t.ceiling_bytes.map(|c| c > t.current_bytes).unwrap_or(true))
+        .map(TableForecast::projected_rate)
+        .sum();
+
+    let (level, detail, days) = match cfg.capacity_bytes {
+        None => (CapacityLevel::Unknown, Some("RANSOMEYE_DB_CAPACITY_GB not set".to_string()), None),
+        Some(_) if history_hours < MIN_HISTORY_HOURS as f64 => (
+            CapacityLevel::Unknown,
+            Some(format!("{:.1}h of samples, {}h needed", history_hours, MIN_HISTORY_HOURS)),
+            None,
+        ),
+        Some(capacity) => {
+            let days = days_until_full(database_bytes, &tables, capacity);
+            let level = match days {
+                Some(d) if d < cfg.alert_days => CapacityLevel::Low,
+                _ => CapacityLevel::Ok,
+            };
+            (level, None, days)
+        }
+    };
+
+    tables.sort_by(|a, b| b.growth_bytes_per_day.total_cmp(&a.growth_bytes_per_day).then_with(|| a.table.cmp(&b.table)));
+    tables.truncate(MAX_REPORTED_TABLES);
+    CapacityForecast {
+        computed_at: now,
+        level,
+        detail,
+        database_bytes,
+        capacity_bytes: cfg.capacity_bytes,
+        used_percent: cfg.capacity_bytes.map(|c| database_bytes as f64 * 100.0 / c as f64),
+        growth_bytes_per_day,
+        days_until_full: days,
+        projected_full_at: days.map(|d| now + Duration::seconds((d * 86_400.0) as i64)),
+        alert_days: cfg.alert_days,
+        samples: samples.len(),
+        history_hours,
+        tables,
+    }
+}
+
+/// Record the current sizes (one db_size_samples row) and drop samples past SAMPLE_KEEP_DAYS.
+pub async fn sample(db: &CoreDb) -> Result<SizeSample, String> {
+    let client = db.client();
+    let database_bytes: i64 = client
+        .query_one("SELECT pg_database_size(current_database())", &[])
+        .await
+        .map_err(|e| format!("Failed to read database size: {e}"))?
+        .get(0);
+    let rows = client
+        .query(
+            r#"
+            SELECT n.nspname || '.' || c.relname, pg_total_relation_size(c.oid)
+            FROM pg_class c
+            JOIN pg_namespace n ON n.oid = c.relnamespace
+            WHERE c.relkind = 'r' AND n.nspname IN ('ransomeye', 'public')
+            "#,
+            &[],
+        )
+        .await
+        .map_err(|e| format!("Failed to read table sizes: {e}"))?;
+    let tables: BTreeMap<String, i64> = rows.iter().map(|r| (r.get(0), r.get(1))).collect();
+
+    let sampled_at = Utc::now();
+    client
+        .execute(
+            "INSERT INTO ransomeye.db_size_samples (sample_id, sampled_at, database_bytes, tables) VALUES ($1, $2, $3, $4)",
+            &[&Uuid::new_v4(), &sampled_at, &database_bytes, &Json(&tables)],
+        )
+        .await
+        .map_err(|e| format!("Failed to insert db_size_samples row: {e}"))?;
+    client
+        .execute(
+            "DELETE FROM ransomeye.db_size_samples WHERE sampled_at < $1",
+            &[&(sampled_at - Duration::days(SAMPLE_KEEP_DAYS))],
+        )
+        .await
+        .map_err(|e| format!("Failed to prune db_size_samples: {e}"))?;
+    Ok(SizeSample { sampled_at, database_bytes, tables })
+}
+
+/// Samples of the last `lookback_days`, oldest first.
+pub async fn history(db: &CoreDb, lookback_days: i64, now: DateTime<Utc>) -> Result<Vec<SizeSample>, String> {
+    let rows = db
+        .client()
+        .query(
+            r#"
+            SELECT sampled_at, database_bytes, tables
+            FROM ransomeye.db_size_samples
+            WHERE sampled_at >= $1
+            ORDER BY sampled_at
+            "#,
+            &[&(now - Duration::days(lookback_days))],
+        )
+        .await
+        .map_err(|e| format!("Failed to read db_size_samples: {e}"))?;
+    Ok(rows
+        .iter()
+        .map(|r| SizeSample {
+            sampled_at: r.get(0),
+            database_bytes: r.get(1),
+            tables: r.get::<_, Json<BTreeMap<String, i64>>>(2).0,
+        })
+        .collect())
+}
+
+/// Enabled retention policies, schema.table -> retention_days (invalid table names are skipped;
+/// the retention enforcer refuses them).
+pub async fn retention_days(db: &CoreDb) -> Result<HashMap<String, i64>, String> {
+    let rows = db
+        .client()
+        .query(
+            "SELECT table_name, retention_days FROM ransomeye.retention_policies WHERE retention_enabled = TRUE",
+            &[],
+        )
+        .await
+        .map_err(|e| format!("Failed to read retention_policies: {e}"))?;
+    Ok(rows
+        .iter()
+        .filter_map(|r| {
+            let table: String = r.get(0);
+            let days: i32 = r.get(1);
+            QualifiedTable::parse(&table).ok().map(|qt| (qt.as_fqn(), days as i64))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const GB: i64 = 1024 * 1024 * 1024;
+
+    fn t0() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 1, 0, 0, 0).unwrap()
+    }
+
+    fn cfg(capacity_gb: i64) -> CapacityConfig {
+        CapacityConfig { sample_interval_secs: 3600, capacity_bytes: Some(capacity_gb * GB), lookback_days: 7, alert_days: 30.0 }
+    }
+
+    /// Daily samples: raw_events grows 1 GB/day, the audit log 0.1 GB/day, scan_results shrinks.
+    fn samples(days: i64) -> Vec<SizeSample> {
+        (0..=days)
+            .map(|d| SizeSample {
+                sampled_at: t0() + Duration::days(d),
+                database_bytes: 12 * GB + d * GB + d * GB / 10 - d * GB / 20,
+                tables: BTreeMap::from([
+                    ("ransomeye.raw_events".to_string(), 5 * GB + d * GB),
+                    ("ransomeye.immutable_audit_log".to_string(), 2 * GB + d * GB / 10),
+                    ("public.scan_results".to_string(), GB - d * GB / 20),
+                ]),
+            })
+            .collect()
+    }
+
+    fn table<'a>(tables: &'a [TableForecast], name: &str) -> &'a TableForecast {
+        tables.iter().find(|t| t.table == name).unwrap()
+    }
+
+    #[test]
+    fn growth_is_fitted_per_day_and_needs_history() {
+        let points = [(t0(), 0), (t0() + Duration::hours(12), GB / 2), (t0() + Duration::days(1), GB)];
+        let growth = growth_per_day(&points).unwrap();
+        assert!((growth - GB as f64).abs() < 1.0);
+        assert_eq!(growth_per_day(&points[..1]), None);
+        assert_eq!(growth_per_day(&[(t0(), 0), (t0() + Duration::hours(MIN_HISTORY_HOURS - 1), GB)]), None);
+    }
+
+    #[test]
+    fn retained_tables_level_off_at_their_policy() {
+        let retention = HashMap::from([("ransomeye.raw_events".to_string(), 30)]);
+        let tables = table_forecasts(&samples(6), &retention);
+        let raw = table(&tables, "ransomeye.raw_events");
+        assert_eq!(raw.retention_days, Some(30));
+        assert_eq!(raw.ceiling_bytes, Some(30 * GB));
+        assert_eq!(table(&tables, "ransomeye.immutable_audit_log").ceiling_bytes, None);
+        assert!(table(&tables, "public.scan_results").growth_bytes_per_day < 0.0);
+
+        // Already past its retention worth of data: the ceiling is its current size
+        let short = HashMap::from([("ransomeye.raw_events".to_string(), 3)]);
+        assert_eq!(table(&table_forecasts(&samples(6), &short), "ransomeye.raw_events").ceiling_bytes, Some(11 * GB));
+    }
+
+    #[test]
+    fn projection_accounts_for_retention_ceilings() {
+        let history = samples(6);
+        let db_bytes = history.last().unwrap().database_bytes;
+        let unretained = table_forecasts(&history, &HashMap::new());
+        // 1.1 GB/day (the shrinking table does not free space) from ~18.3 GB to 40 GB
+        let days = days_until_full(db_bytes, &unretained, 40 * GB).unwrap();
+        assert!((days - (40 * GB - db_bytes) as f64 / (1.1 * GB as f64)).abs() < 0.01, "{days}");
+
+        // raw_events stops at 30 GB after 19 more days; then only the audit log grows
+        let retained = table_forecasts(&history, &HashMap::from([("ransomeye.raw_events".to_string(), 30)]));
+        let days = days_until_full(db_bytes, &retained, 40 * GB).unwrap();
+        let at_ceiling = db_bytes as f64 + 19.0 * 1.1 * GB as f64;
+        assert!((days - (19.0 + (40.0 * GB as f64 - at_ceiling) / (0.1 * GB as f64))).abs() < 0.01, "{days}");
+
+        assert_eq!(days_until_full(db_bytes, &retained, 10 * GB), Some(0.0));
+        // Every growing table retained: levels off below capacity
+        let all = HashMap::from([
+            ("ransomeye.raw_events".to_string(), 30),
+            ("ransomeye.immutable_audit_log".to_string(), 30),
+        ]);
+        assert_eq!(days_until_full(db_bytes, &table_forecasts(&history, &all), 100 * GB), None);
+    }
+
+    #[test]
+    fn level_follows_the_alert_threshold() {
+        let now = t0() + Duration::days(6);
+        let low = forecast(&samples(6), &HashMap::new(), &cfg(40), now);
+        assert_eq!(low.level, CapacityLevel::Low);
+        assert_eq!(low.tables[0].table, "ransomeye.raw_events");
+        assert!(low.projected_full_at.unwrap() > now);
+
+        assert_eq!(forecast(&samples(6), &HashMap::new(), &cfg(100), now).level, CapacityLevel::Ok);
+        let unset = CapacityConfig { capacity_bytes: None, ..cfg(40) };
+        assert_eq!(forecast(&samples(6), &HashMap::new(), &unset, now).level, CapacityLevel::Unknown);
+        let fresh = forecast(&samples(0), &HashMap::new(), &cfg(40), now);
+        assert_eq!(fresh.level, CapacityLevel::Unknown);
+        assert!(fresh.detail.unwrap().contains("needed"));
+    }
+}
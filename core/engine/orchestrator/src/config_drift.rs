@@ -0,0 +1,253 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/orchestrator/src/config_drift.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Environment fingerprint capture and config drift detection between the startup-registered fingerprint (startup_events) and the current environment.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use sha2::{Digest, Sha256};
+
+/// Non-secret variables that make up the environment fingerprint (never DB_PASS or other secrets).
+pub const FINGERPRINT_VARS: &[&str] = &[
+    "DB_HOST",
+    "DB_PORT",
+    "DB_NAME",
+    "DB_USER",
+    "RANSOMEYE_ROOT_KEY_PATH",
+    "RANSOMEYE_POLICY_DIR",
+    "RANSOMEYE_TRUST_STORE_PATH",
+    "RANSOMEYE_SCHEMA_SQL_PATH",
+];
+
+/// Variables whose change invalidates the trust/DB contract established at startup.
+pub const DEFAULT_CRITICAL_VARS: &[&str] = &[
+    "DB_HOST",
+    "DB_NAME",
+    "RANSOMEYE_ROOT_KEY_PATH",
+    "RANSOMEYE_TRUST_STORE_PATH",
+    "RANSOMEYE_SCHEMA_SQL_PATH",
+];
+
+const DEFAULT_RUNTIME_ENV_FILE: &str = "/etc/ransomeye/ransomeye.runtime.env";
+
+#[derive(Debug, Clone)]
+pub struct DriftConfig {
+    /// Periodic check interval; 0 disables periodic checks (reload checks still run).
+    pub check_interval_secs: u64,
+    /// Mark the component unhealthy when a critical variable drifts.
+    pub unhealthy_on_critical: bool,
+    pub critical_vars: Vec<String>,
+    /// Optional env file re-read on every check (the running process env never changes on its own).
+    pub env_file: Option<String>,
+}
+
+impl DriftConfig {
+    pub fn from_env() -> Result<Self, String> {
+        let check_interval_secs = match std::env::var("RANSOMEYE_DRIFT_CHECK_INTERVAL_SECS") {
+            Ok(v) => v
+                .parse::<u64>()
+                .map_err(|e| format!("FAIL-CLOSED: Invalid RANSOMEYE_DRIFT_CHECK_INTERVAL_SECS='{v}': {e}"))?,
+            Err(_) => 300,
+        };
+
+        let unhealthy_on_critical = match std::env::var("RANSOMEYE_DRIFT_UNHEALTHY_ON_CRITICAL") {
+            Ok(v) => v == "1" || v.eq_ignore_ascii_case("true"),
+            Err(_) => true,
+        };
+
+        let critical_vars: Vec<String> = match std::env::var("RANSOMEYE_DRIFT_CRITICAL_VARS") {
+            Ok(v) => v
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            Err(_) => DEFAULT_CRITICAL_VARS.iter().map(|s| s.to_string()).collect(),
+        };
+        for var in &critical_vars {
+            if !FINGERPRINT_VARS.contains(&var.as_str()) {
+                return Err(format!(
+                    "FAIL-CLOSED: RANSOMEYE_DRIFT_CRITICAL_VARS contains '{var}' which is not part of the env fingerprint"
+                ));
+            }
+        }
+
+        let env_file = match std::env::var("RANSOMEYE_DRIFT_ENV_FILE") {
+            Ok(v) if v.is_empty() => None,
+            Ok(v) => Some(v),
+            Err(_) if Path::new(DEFAULT_RUNTIME_ENV_FILE).exists() => Some(DEFAULT_RUNTIME_ENV_FILE.to_string()),
+            Err(_) => None,
+        };
+
+        Ok(Self {
+            check_interval_secs,
+            unhealthy_on_critical,
+            critical_vars,
+            env_file,
+        })
+    }
+}
+
+/// Snapshot of the fingerprinted variables (absent variables are `None`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvSnapshot {
+    values: BTreeMap<String, Option<String>>,
+}
+
+impl EnvSnapshot {
+    /// Capture from the process environment, overlaid with `env_file` if given and readable.
+    pub fn capture(env_file: Option<&str>) -> Self {
+        let overlay = env_file
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .map(|s| parse_env_file(&s))
+            .unwrap_or_default();
+
+        let mut values = BTreeMap::new();
+        for k in FINGERPRINT_VARS {
+            let v = overlay
+                .get(*k)
+                .cloned()
+                .or_else(|| std::env::var(k).ok());
+            values.insert(k.to_string(), v);
+        }
+        Self { values }
+    }
+
+    pub fn from_pairs(pairs: &[(&str, &str)]) -> Self {
+        let mut values: BTreeMap<String, Option<String>> =
+            FINGERPRINT_VARS.iter().map(|k| (k.to_string(), None)).collect();
+        for (k, v) in pairs {
+            if values.contains_key(*k) {
+                values.insert(k.to_string(), Some(v.to_string()));
+            }
+        }
+        Self { values }
+    }
+
+    /// SHA-256 over `KEY=value\n` lines of present variables, sorted by key.
+    pub fn fingerprint(&self) -> Vec<u8> {
+        let mut canonical = String::new();
+        for (k, v) in &self.values {
+            if let Some(v) = v {
+                canonical.push_str(k);
+                canonical.push('=');
+                canonical.push_str(v);
+                canonical.push('\n');
+            }
+        }
+        let mut hasher = Sha256::new();
+        hasher.update(canonical.as_bytes());
+        hasher.finalize().to_vec()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DriftReport {
+    /// Names only; values are not reported to keep audit payloads free of host paths/credentials.
+    pub changed_vars: Vec<String>,
+    pub critical_changed: Vec<String>,
+    pub startup_fingerprint_hex: String,
+    pub current_fingerprint_hex: String,
+}
+
+impl DriftReport {
+    pub fn is_critical(&self) -> bool {
+        !self.critical_changed.is_empty()
+    }
+}
+
+pub fn detect_drift(startup: &EnvSnapshot, current: &EnvSnapshot, critical_vars: &[String]) -> Option<DriftReport> {
+    let startup_fp = startup.fingerprint();
+    let current_fp = current.fingerprint();
+    if startup_fp == current_fp {
+        return None;
+    }
+
+    let mut changed_vars = Vec::new();
+    for (k, v) in &startup.values {
+        if current.values.get(k) != Some(v) {
+            changed_vars.push(k.clone());
+        }
+    }
+    let critical_changed = changed_vars
+        .iter()
+        .filter(|k| critical_vars.iter().any(|c| c == *k))
+        .cloned()
+        .collect();
+
+    Some(DriftReport {
+        changed_vars,
+        critical_changed,
+        startup_fingerprint_hex: hex_lower(&startup_fp),
+        current_fingerprint_hex: hex_lower(&current_fp),
+    })
+}
+
+/// Minimal systemd EnvironmentFile parser: KEY=VALUE lines, `#` comments, optional quotes.
+fn parse_env_file(contents: &str) -> BTreeMap<String, String> {
+    let mut out = BTreeMap::new();
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        if let Some((k, v)) = line.split_once('=') {
+            let v = v.trim();
+            let v = v
+                .strip_prefix('"')
+                .and_then(|s| s.strip_suffix('"'))
+                .or_else(|| v.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')))
+                .unwrap_or(v);
+            out.insert(k.trim().to_string(), v.to_string());
+        }
+    }
+    out
+}
+
+fn hex_lower(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn critical() -> Vec<String> {
+        DEFAULT_CRITICAL_VARS.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn fingerprint_matches_startup_canonical_form() {
+        let snap = EnvSnapshot::from_pairs(&[("DB_PORT", "5432"), ("DB_HOST", "db")]);
+        let mut hasher = Sha256::new();
+        hasher.update(b"DB_HOST=db\nDB_PORT=5432\n");
+        assert_eq!(snap.fingerprint(), hasher.finalize().to_vec());
+    }
+
+    #[test]
+    fn no_drift_when_identical() {
+        let a = EnvSnapshot::from_pairs(&[("DB_HOST", "db")]);
+        assert!(detect_drift(&a, &a.clone(), &critical()).is_none());
+    }
+
+    #[test]
+    fn drift_reports_critical_and_non_critical() {
+        let a = EnvSnapshot::from_pairs(&[("DB_HOST", "db"), ("DB_PORT", "5432")]);
+        let b = EnvSnapshot::from_pairs(&[("DB_HOST", "db"), ("DB_PORT", "6432")]);
+        let r = detect_drift(&a, &b, &critical()).unwrap();
+        assert_eq!(r.changed_vars, vec!["DB_PORT".to_string()]);
+        assert!(!r.is_critical());
+
+        let c = EnvSnapshot::from_pairs(&[("DB_HOST", "other"), ("DB_PORT", "5432")]);
+        let r = detect_drift(&a, &c, &critical()).unwrap();
+        assert_eq!(r.critical_changed, vec!["DB_HOST".to_string()]);
+    }
+
+    #[test]
+    fn env_file_parser_handles_quotes_and_comments() {
+        let m = parse_env_file("# comment\nexport DB_HOST=\"db\"\nDB_PORT='5432'\n\nBAD\n");
+        assert_eq!(m.get("DB_HOST").map(String::as_str), Some("db"));
+        assert_eq!(m.get("DB_PORT").map(String::as_str), Some("5432"));
+        assert_eq!(m.len(), 2);
+    }
+}
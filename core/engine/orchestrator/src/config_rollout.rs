@@ -0,0 +1,673 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/orchestrator/src/config_rollout.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Agent config rollout rings - canary assignment of a new agent config version, ring promotion after a health soak, automatic rollback when canary agents' error or drop rates regress against the rest of the fleet
+
+/*
+ * Config Rollout Rings
+ *
+ * A rollout moves one agent type to a new agent_config_versions row through rings of increasing
+ * fleet percentage (default 5,25,100). Each agent falls in a stable bucket 0..99 derived from
+ * SHA-256(rollout_id | agent_id); it receives the new version once its bucket is below the current
+ * ring's percentage, so each ring contains the previous one. agent_config_assignments holds the
+ * desired version per agent (and the version it replaced) for the delivery channel to push.
+ *
+ * Every poll the controller assigns newly eligible agents (agents enrolled mid-rollout included)
+ * and compares the canary cohort (agents on this rollout) with the control cohort (the rest of
+ * the agent type) over the current ring's soak window, from agent_stats telemetry:
+ *   error rate  share of agent_stats samples with the delivery circuit not closed
+ *   drop rate   events shed per agent-hour (growth of the cumulative per-priority drop counters)
+ * A canary rate exceeding the control rate by more than the allowed delta rolls the rollout back
+ * at once: every assigned agent returns to the version it had before. A clean ring is promoted
+ * after its soak; the last ring (100%) completes the rollout. Rings whose canary cohort has not
+ * reported yet keep soaking.
+ *
+ * One rollout per agent type is active at a time. Transitions are written to the audit log.
+ */
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde_json::Value as JsonValue;
+use sha2::{Digest, Sha256};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use super::db::{CoreDb, DbConfig};
+
+pub const DEFAULT_RINGS: &str = "5,25,100";
+
+#[derive(Debug, Clone)]
+pub struct RolloutConfig {
+    pub enabled: bool,
+    pub poll_interval: Duration,
+    pub health: HealthThresholds,
+}
+
+/// Regression thresholds (canary minus control)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HealthThresholds {
+    pub max_error_rate_delta: f64,
+    /// Events shed per agent-hour
+    pub max_drop_rate_delta: f64,
+    /// Canary agents that must have reported before a ring may be promoted
+    pub min_reporting_agents: u64,
+}
+
+fn env_f64(key: &str, default_value: f64) -> Result<f64, String> {
+    match std::env::var(key) {
+        Ok(v) => v
+            .parse::<f64>()
+            .ok()
+            .filter(|n| n.is_finite() && *n >= 0.0)
+            .ok_or_else(|| format!("Invalid {}='{}' (expected number >= 0)", key, v)),
+        Err(_) => Ok(default_value),
+    }
+}
+
+fn env_u64(key: &str, default_value: u64) -> Result<u64, String> {
+    match std::env::var(key) {
+        Ok(v) => v
+            .parse::<u64>()
+            .ok()
+            .filter(|n| *n > 0)
+            .ok_or_else(|| format!("Invalid {}='{}' (expected integer > 0)", key, v)),
+        Err(_) => Ok(default_value),
+    }
+}
+
+impl RolloutConfig {
+    pub fn from_env() -> Result<Self, String> {
+        let enabled = std::env::var("RANSOMEYE_CONFIG_ROLLOUT_ENABLED")
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(true);
+        Ok(Self {
+            enabled,
+            poll_interval: Duration::from_secs(env_u64("RANSOMEYE_CONFIG_ROLLOUT_POLL_SECS", 60)?),
+            health: HealthThresholds {
+                max_error_rate_delta: env_f64("RANSOMEYE_CONFIG_ROLLOUT_MAX_ERROR_RATE_DELTA", 0.05)?,
+                max_drop_rate_delta: env_f64("RANSOMEYE_CONFIG_ROLLOUT_MAX_DROP_RATE_DELTA", 100.0)?,
+                min_reporting_agents: env_u64("RANSOMEYE_CONFIG_ROLLOUT_MIN_REPORTING", 1)?,
+            },
+        })
+    }
+}
+
+/// Ring percentages: strictly increasing, 1..=100, ending at 100.
+pub fn parse_rings(raw: &str) -> Result<Vec<i16>, String> {
+    let rings = raw
+        .split(',')
+        .map(|p| p.trim().parse::<i16>().map_err(|_| format!("invalid ring percentage '{}'", p.trim())))
+        .collect::<Result<Vec<_>, _>>()?;
+    if rings.iter().any(|p| !(1..=100).contains(p)) {
+        return Err("ring percentages must be between 1 and 100".to_string());
+    }
+    if rings.windows(2).any(|w| w[0] >= w[1]) {
+        return Err("ring percentages must be strictly increasing".to_string());
+    }
+    if rings.last() != Some(&100) {
+        return Err("the last ring must be 100".to_string());
+    }
+    Ok(rings)
+}
+
+/// Stable bucket 0..99 of an agent within a rollout
+pub fn ring_bucket(rollout_id: Uuid, agent_id: Uuid) -> i16 {
+    let digest = Sha256::new().chain_update(rollout_id.as_bytes()).chain_update(agent_id.as_bytes()).finalize();
+    (u16::from_be_bytes([digest[0], digest[1]]) % 100) as i16
+}
+
+/// agent_stats figures of one cohort over the soak window
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CohortHealth {
+    /// Agents with at least one agent_stats sample in the window
+    pub reporting_agents: u64,
+    pub samples: u64,
+    pub erroring_samples: u64,
+    /// Events shed (sum of per-agent counter growth)
+    pub dropped: u64,
+    pub window_hours: f64,
+}
+
+impl CohortHealth {
+    pub fn error_rate(&self) -> f64 {
+        if self.samples == 0 {
+            return 0.0;
+        }
+        self.erroring_samples as f64 / self.samples as f64
+    }
+
+    pub fn drop_rate(&self) -> f64 {
+        if self.reporting_agents == 0 || self.window_hours <= 0.0 {
+            return 0.0;
+        }
+        self.dropped as f64 / self.reporting_agents as f64 / self.window_hours
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RolloutDecision {
+    /// Keep soaking the current ring
+    Soak,
+    Promote { ring: i16 },
+    Complete,
+    Rollback { reason: String },
+}
+
+/// Decide the next step of `rollout` from its cohorts' health at `now`.
+pub fn evaluate(
+    rollout: &ActiveRollout,
+    now: DateTime<Utc>,
+    canary: &CohortHealth,
+    control: &CohortHealth,
+    thresholds: &HealthThresholds,
+) -> RolloutDecision {
+    if canary.reporting_agents > 0 {
+        let error_delta = canary.error_rate() - control.error_rate();
+        if error_delta > thresholds.max_error_rate_delta {
+            return RolloutDecision::Rollback {
+                reason: format!(
+                    "error rate regressed: canary {:.4} vs control {:.4} (max delta {})",
+                    canary.error_rate(),
+                    control.error_rate(),
+                    thresholds.max_error_rate_delta
+                ),
+            };
+        }
+        let drop_delta = canary.drop_rate() - control.drop_rate();
+        if drop_delta > thresholds.max_drop_rate_delta {
+            return RolloutDecision::Rollback {
+                reason: format!(
+                    "drop rate regressed: canary {:.1}/agent-hour vs control {:.1} (max delta {})",
+                    canary.drop_rate(),
+                    control.drop_rate(),
+                    thresholds.max_drop_rate_delta
+                ),
+            };
+        }
+    }
+    let soaked = now.signed_duration_since(rollout.ring_started_at).to_std().is_ok_and(|elapsed| elapsed >= rollout.soak);
+    if !soaked || canary.reporting_agents < thresholds.min_reporting_agents {
+        return RolloutDecision::Soak;
+    }
+    let next = rollout.current_ring + 1;
+    if (next as usize) < rollout.rings.len() {
+        RolloutDecision::Promote { ring: next }
+    } else {
+        RolloutDecision::Complete
+    }
+}
+
+/// An active rollout row
+#[derive(Debug, Clone)]
+pub struct ActiveRollout {
+    pub rollout_id: Uuid,
+    pub config_version_id: Uuid,
+    pub agent_type: String,
+    pub rings: Vec<i16>,
+    pub current_ring: i16,
+    pub ring_started_at: DateTime<Utc>,
+    pub soak: Duration,
+}
+
+/// Register a config version and start its rollout at ring 0. Fails if the agent type already
+/// has an active rollout.
+pub async fn start(
+    db: &CoreDb,
+    agent_type: &str,
+    config: &JsonValue,
+    description: Option<&str>,
+    created_by: &str,
+    rings: &[i16],
+    soak: Duration,
+) -> Result<Uuid, String> {
+    let config_bytes = serde_json::to_vec(config).map_err(|e| format!("config serialization: {e}"))?;
+    let config_sha256 = Sha256::digest(&config_bytes).to_vec();
+    let config_version_id = Uuid::new_v4();
+    let rollout_id = Uuid::new_v4();
+    let client = db.client();
+    client.batch_execute("BEGIN").await.map_err(|e| format!("Failed to begin rollout transaction: {e}"))?;
+    let result = async {
+        client
+            .execute(
+                r#"
+                INSERT INTO agent_config_versions (config_version_id, agent_type, config, config_sha256, description, created_by)
+                VALUES ($1, $2::text::event_source_type, $3, $4, $5, $6)
+                "#,
+                &[&config_version_id, &agent_type, config, &config_sha256, &description, &created_by],
+            )
+            .await
+            .map_err(|e| format!("Failed to insert agent config version: {e}"))?;
+        client
+            .execute(
+                r#"
+                INSERT INTO agent_config_rollouts (rollout_id, config_version_id, agent_type, rings, soak_secs, started_by)
+                VALUES ($1, $2, $3::text::event_source_type, $4, $5, $6)
+                "#,
+                &[&rollout_id, &config_version_id, &agent_type, &rings, &(soak.as_secs() as i64), &created_by],
+            )
+            .await
+            .map_err(|e| format!("Failed to start rollout (is one already active for {agent_type}?): {e}"))?;
+        Ok::<(), String>(())
+    }
+    .await;
+    match result {
+        Ok(()) => client.batch_execute("COMMIT").await.map_err(|e| format!("Failed to commit rollout: {e}"))?,
+        Err(e) => {
+            let _ = client.batch_execute("ROLLBACK").await;
+            return Err(e);
+        }
+    }
+    db.insert_immutable_audit_log(
+        None,
+        "AGENT_CONFIG_ROLLOUT_STARTED",
+        "other",
+        Some(rollout_id),
+        &serde_json::json!({
+            "rollout_id": rollout_id,
+            "config_version_id": config_version_id,
+            "agent_type": agent_type,
+            "rings": rings,
+            "soak_secs": soak.as_secs(),
+            "started_by": created_by,
+        }),
+    )
+    .await?;
+    Ok(rollout_id)
+}
+
+/// Operator abort: roll every assigned agent back to its previous version.
+pub async fn abort(db: &CoreDb, agent_type: &str, reason: &str) -> Result<Option<Uuid>, String> {
+    let rollouts = active_rollouts(db).await?;
+    let Some(rollout) = rollouts.into_iter().find(|r| r.agent_type == agent_type) else {
+        return Ok(None);
+    };
+    roll_back(db, &rollout, &format!("aborted: {reason}")).await?;
+    Ok(Some(rollout.rollout_id))
+}
+
+pub async fn active_rollouts(db: &CoreDb) -> Result<Vec<ActiveRollout>, String> {
+    let rows = db
+        .client()
+        .query(
+            r#"
+            SELECT rollout_id, config_version_id, agent_type::text, rings, current_ring, ring_started_at, soak_secs
+            FROM agent_config_rollouts
+            WHERE status = 'active'
+            ORDER BY started_at
+            "#,
+            &[],
+        )
+        .await
+        .map_err(|e| format!("Failed to load active rollouts: {e}"))?;
+    Ok(rows
+        .iter()
+        .map(|r| ActiveRollout {
+            rollout_id: r.get(0),
+            config_version_id: r.get(1),
+            agent_type: r.get(2),
+            rings: r.get(3),
+            current_ring: r.get(4),
+            ring_started_at: r.get(5),
+            soak: Duration::from_secs(r.get::<_, i64>(6).max(0) as u64),
+        })
+        .collect())
+}
+
+async fn roll_back(db: &CoreDb, rollout: &ActiveRollout, reason: &str) -> Result<(), String> {
+    let client = db.client();
+    client.batch_execute("BEGIN").await.map_err(|e| format!("Failed to begin rollback: {e}"))?;
+    let result = async {
+        let restored = client
+            .execute(
+                r#"
+                UPDATE agent_config_assignments
+                SET config_version_id = previous_config_version_id, previous_config_version_id = NULL,
+                    rollout_id = NULL, assigned_at = now()
+                WHERE rollout_id = $1 AND previous_config_version_id IS NOT NULL
+                "#,
+                &[&rollout.rollout_id],
+            )
+            .await
+            .map_err(|e| format!("Failed to restore previous agent configs: {e}"))?;
+        // Agents that had no managed config before the rollout go back to their local config
+        let cleared = client
+            .execute("DELETE FROM agent_config_assignments WHERE rollout_id = $1", &[&rollout.rollout_id])
+            .await
+            .map_err(|e| format!("Failed to clear rollout assignments: {e}"))?;
+        client
+            .execute(
+                r#"
+                UPDATE agent_config_rollouts
+                SET status = 'rolled_back', finished_at = now(), rollback_reason = $2
+                WHERE rollout_id = $1
+                "#,
+                &[&rollout.rollout_id, &reason],
+            )
+            .await
+            .map_err(|e| format!("Failed to mark rollout rolled back: {e}"))?;
+        Ok::<(u64, u64), String>((restored, cleared))
+    }
+    .await;
+    let (restored, cleared) = match result {
+        Ok(counts) => {
+            client.batch_execute("COMMIT").await.map_err(|e| format!("Failed to commit rollback: {e}"))?;
+            counts
+        }
+        Err(e) => {
+            let _ = client.batch_execute("ROLLBACK").await;
+            return Err(e);
+        }
+    };
+    warn!(
+        "Config rollout {} ({}) ROLLED BACK: {} (restored={}, cleared={})",
+        rollout.rollout_id, rollout.agent_type, reason, restored, cleared
+    );
+    db.insert_immutable_audit_log(
+        None,
+        "AGENT_CONFIG_ROLLOUT_ROLLED_BACK",
+        "other",
+        Some(rollout.rollout_id),
+        &serde_json::json!({
+            "rollout_id": rollout.rollout_id,
+            "agent_type": rollout.agent_type,
+            "ring": rollout.current_ring,
+            "reason": reason,
+            "agents_restored": restored,
+            "agents_cleared": cleared,
+        }),
+    )
+    .await?;
+    Ok(())
+}
+
+pub struct RolloutController {
+    cfg: RolloutConfig,
+    db: CoreDb,
+}
+
+impl RolloutController {
+    /// Own connection: rollout transitions run BEGIN/COMMIT batches of their own.
+    pub async fn connect(cfg: RolloutConfig, db_cfg: &DbConfig) -> Result<Self, String> {
+        let db = CoreDb::connect_strict(db_cfg).await?;
+        Ok(Self { cfg, db })
+    }
+
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            info!("Config rollout controller running (poll={}s)", self.cfg.poll_interval.as_secs());
+            let mut tick = tokio::time::interval(self.cfg.poll_interval);
+            loop {
+                tick.tick().await;
+                if let Err(e) = self.run_once().await {
+                    error!("Config rollout pass failed: {}", e);
+                }
+            }
+        })
+    }
+
+    /// One pass over the active rollouts; returns the decisions taken.
+    pub async fn run_once(&self) -> Result<Vec<(Uuid, RolloutDecision)>, String> {
+        let mut decisions = Vec::new();
+        for rollout in active_rollouts(&self.db).await? {
+            let assigned = self.assign(&rollout).await?;
+            if assigned > 0 {
+                info!(
+                    "Config rollout {} ring {} ({}%): {} agents assigned",
+                    rollout.rollout_id, rollout.current_ring, rollout.rings[rollout.current_ring as usize], assigned
+                );
+            }
+            let (canary, control) = self.cohort_health(&rollout).await?;
+            let now = Utc::now();
+            let decision = evaluate(&rollout, now, &canary, &control, &self.cfg.health);
+            match &decision {
+                RolloutDecision::Soak => {}
+                RolloutDecision::Promote { ring } => self.promote(&rollout, *ring, &canary, &control).await?,
+                RolloutDecision::Complete => self.complete(&rollout, &canary, &control).await?,
+                RolloutDecision::Rollback { reason } => roll_back(&self.db, &rollout, reason).await?,
+            }
+            decisions.push((rollout.rollout_id, decision));
+        }
+        Ok(decisions)
+    }
+
+    /// Assign the rollout's version to active agents of its type whose bucket is in the current ring.
+    async fn assign(&self, rollout: &ActiveRollout) -> Result<u64, String> {
+        let percent = rollout.rings[rollout.current_ring as usize];
+        let rows = self
+            .db
+            .client()
+            .query(
+                r#"
+                SELECT a.agent_id
+                FROM agents a
+                LEFT JOIN agent_config_assignments s ON s.agent_id = a.agent_id
+                WHERE a.agent_type::text = $1 AND a.is_active AND a.decommissioned_at IS NULL
+                  AND s.rollout_id IS DISTINCT FROM $2
+                "#,
+                &[&rollout.agent_type, &rollout.rollout_id],
+            )
+            .await
+            .map_err(|e| format!("Failed to list agents for rollout: {e}"))?;
+        let eligible: Vec<Uuid> = rows
+            .iter()
+            .map(|r| r.get::<_, Uuid>(0))
+            .filter(|agent_id| ring_bucket(rollout.rollout_id, *agent_id) < percent)
+            .collect();
+        if eligible.is_empty() {
+            return Ok(0);
+        }
+        self.db
+            .client()
+            .execute(
+                r#"
+                INSERT INTO agent_config_assignments (agent_id, config_version_id, rollout_id, ring, assigned_at)
+                SELECT unnest($1::uuid[]), $2, $3, $4, now()
+                ON CONFLICT (agent_id) DO UPDATE
+                SET previous_config_version_id = agent_config_assignments.config_version_id,
+                    config_version_id = EXCLUDED.config_version_id,
+                    rollout_id = EXCLUDED.rollout_id,
+                    ring = EXCLUDED.ring,
+                    assigned_at = EXCLUDED.assigned_at
+                "#,
+                &[&eligible, &rollout.config_version_id, &rollout.rollout_id, &rollout.current_ring],
+            )
+            .await
+            .map_err(|e| format!("Failed to assign rollout config: {e}"))
+    }
+
+    /// (canary, control) health since the current ring started
+    async fn cohort_health(&self, rollout: &ActiveRollout) -> Result<(CohortHealth, CohortHealth), String> {
+        let rows = self
+            .db
+            .client()
+            .query(
+                r#"
+                WITH samples AS (
+                  SELECT t.agent_id,
+                         COALESCE((t.payload->'agent_stats'->'dropped_by_priority'->>'critical')::bigint, 0)
+                       + COALESCE((t.payload->'agent_stats'->'dropped_by_priority'->>'process_exec')::bigint, 0)
+                       + COALESCE((t.payload->'agent_stats'->'dropped_by_priority'->>'telemetry')::bigint, 0)
+                       + COALESCE((t.payload->'agent_stats'->'dropped_by_priority'->>'stats')::bigint, 0) AS dropped,
+                         COALESCE(t.payload->'agent_stats'->>'circuit_state', 'closed') <> 'closed' AS erroring
+                  FROM linux_agent_telemetry t
+                  JOIN agents a ON a.agent_id = t.agent_id
+                  WHERE t.event_category = 'agent_stats' AND t.observed_at >= $1 AND a.agent_type::text = $3
+                ), per_agent AS (
+                  SELECT agent_id, GREATEST(max(dropped) - min(dropped), 0) AS dropped,
+                         count(*) AS samples, count(*) FILTER (WHERE erroring) AS erroring
+                  FROM samples
+                  GROUP BY agent_id
+                )
+                SELECT COALESCE(s.rollout_id = $2, false) AS canary,
+                       count(*)::bigint, COALESCE(sum(p.samples), 0)::bigint,
+                       COALESCE(sum(p.erroring), 0)::bigint, COALESCE(sum(p.dropped), 0)::bigint
+                FROM per_agent p
+                LEFT JOIN agent_config_assignments s ON s.agent_id = p.agent_id
+                GROUP BY 1
+                "#,
+                &[&rollout.ring_started_at, &rollout.rollout_id, &rollout.agent_type],
+            )
+            .await
+            .map_err(|e| format!("Failed to compute rollout cohort health: {e}"))?;
+        let window_hours = (Utc::now() - rollout.ring_started_at).num_seconds().max(1) as f64 / 3600.0;
+        let mut canary = CohortHealth { window_hours, ..Default::default() };
+        let mut control = canary;
+        for row in rows {
+            let cohort = if row.get::<_, bool>(0) { &mut canary } else { &mut control };
+            cohort.reporting_agents = row.get::<_, i64>(1).max(0) as u64;
+            cohort.samples = row.get::<_, i64>(2).max(0) as u64;
+            cohort.erroring_samples = row.get::<_, i64>(3).max(0) as u64;
+            cohort.dropped = row.get::<_, i64>(4).max(0) as u64;
+        }
+        Ok((canary, control))
+    }
+
+    async fn promote(&self, rollout: &ActiveRollout, ring: i16, canary: &CohortHealth, control: &CohortHealth) -> Result<(), String> {
+        self.db
+            .client()
+            .execute(
+                "UPDATE agent_config_rollouts SET current_ring = $2, ring_started_at = now() WHERE rollout_id = $1 AND status = 'active'",
+                &[&rollout.rollout_id, &ring],
+            )
+            .await
+            .map_err(|e| format!("Failed to promote rollout: {e}"))?;
+        info!(
+            "Config rollout {} ({}) promoted to ring {} ({}%)",
+            rollout.rollout_id, rollout.agent_type, ring, rollout.rings[ring as usize]
+        );
+        self.audit_transition("AGENT_CONFIG_ROLLOUT_PROMOTED", rollout, Some(ring), canary, control).await
+    }
+
+    async fn complete(&self, rollout: &ActiveRollout, canary: &CohortHealth, control: &CohortHealth) -> Result<(), String> {
+        self.db
+            .client()
+            .execute(
+                "UPDATE agent_config_rollouts SET status = 'completed', finished_at = now() WHERE rollout_id = $1 AND status = 'active'",
+                &[&rollout.rollout_id],
+            )
+            .await
+            .map_err(|e| format!("Failed to complete rollout: {e}"))?;
+        info!("Config rollout {} ({}) completed", rollout.rollout_id, rollout.agent_type);
+        self.audit_transition("AGENT_CONFIG_ROLLOUT_COMPLETED", rollout, None, canary, control).await
+    }
+
+    async fn audit_transition(
+        &self,
+        action: &str,
+        rollout: &ActiveRollout,
+        ring: Option<i16>,
+        canary: &CohortHealth,
+        control: &CohortHealth,
+    ) -> Result<(), String> {
+        let cohort = |c: &CohortHealth| {
+            serde_json::json!({
+                "reporting_agents": c.reporting_agents,
+                "error_rate": c.error_rate(),
+                "drop_rate": c.drop_rate(),
+            })
+        };
+        self.db
+            .insert_immutable_audit_log(
+                None,
+                action,
+                "other",
+                Some(rollout.rollout_id),
+                &serde_json::json!({
+                    "rollout_id": rollout.rollout_id,
+                    "agent_type": rollout.agent_type,
+                    "from_ring": rollout.current_ring,
+                    "to_ring": ring,
+                    "canary": cohort(canary),
+                    "control": cohort(control),
+                }),
+            )
+            .await
+            .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn thresholds() -> HealthThresholds {
+        HealthThresholds { max_error_rate_delta: 0.05, max_drop_rate_delta: 100.0, min_reporting_agents: 2 }
+    }
+
+    fn healthy(agents: u64) -> CohortHealth {
+        CohortHealth { reporting_agents: agents, samples: agents * 10, erroring_samples: 0, dropped: 0, window_hours: 1.0 }
+    }
+
+    fn rollout(current_ring: i16, ring_started_at: DateTime<Utc>) -> ActiveRollout {
+        ActiveRollout {
+            rollout_id: Uuid::new_v4(),
+            config_version_id: Uuid::new_v4(),
+            agent_type: "linux_agent".to_string(),
+            rings: vec![5, 25, 100],
+            current_ring,
+            ring_started_at,
+            soak: Duration::from_secs(3600),
+        }
+    }
+
+    #[test]
+    fn rings_must_increase_to_100() {
+        assert_eq!(parse_rings(DEFAULT_RINGS).unwrap(), vec![5, 25, 100]);
+        assert_eq!(parse_rings("100").unwrap(), vec![100]);
+        assert!(parse_rings("5,5,100").is_err());
+        assert!(parse_rings("5,25").is_err());
+        assert!(parse_rings("0,100").is_err());
+        assert!(parse_rings("5,x,100").is_err());
+    }
+
+    #[test]
+    fn buckets_are_stable_and_spread() {
+        let rollout = Uuid::new_v4();
+        let agents: Vec<Uuid> = (0..2000).map(|_| Uuid::new_v4()).collect();
+        assert!(agents.iter().all(|a| ring_bucket(rollout, *a) == ring_bucket(rollout, *a)));
+        let in_canary = agents.iter().filter(|a| ring_bucket(rollout, **a) < 5).count();
+        // ~5% of 2000; generous bounds keep this deterministic enough
+        assert!((40..=180).contains(&in_canary), "canary size {}", in_canary);
+    }
+
+    #[test]
+    fn soak_then_promote_then_complete() {
+        let started = Utc::now() - chrono::Duration::seconds(600);
+        let soaked = started + chrono::Duration::seconds(3600);
+        let r0 = rollout(0, started);
+        assert_eq!(evaluate(&r0, Utc::now(), &healthy(3), &healthy(50), &thresholds()), RolloutDecision::Soak);
+        assert_eq!(
+            evaluate(&r0, soaked, &healthy(3), &healthy(50), &thresholds()),
+            RolloutDecision::Promote { ring: 1 }
+        );
+        // Not enough canary agents reported: keep soaking
+        assert_eq!(evaluate(&rollout(1, started), soaked, &healthy(1), &healthy(50), &thresholds()), RolloutDecision::Soak);
+        assert_eq!(
+            evaluate(&rollout(2, started), soaked, &healthy(60), &CohortHealth::default(), &thresholds()),
+            RolloutDecision::Complete
+        );
+    }
+
+    #[test]
+    fn regression_rolls_back_before_soak_ends() {
+        let now = Utc::now();
+        let r0 = rollout(0, now);
+        let mut erroring = healthy(3);
+        erroring.erroring_samples = 6; // 20% vs 0%
+        assert!(matches!(
+            evaluate(&r0, now, &erroring, &healthy(50), &thresholds()),
+            RolloutDecision::Rollback { .. }
+        ));
+
+        let mut dropping = healthy(3);
+        dropping.dropped = 600; // 200 per agent-hour
+        let mut control = healthy(50);
+        control.dropped = 2500; // 50 per agent-hour
+        assert!(matches!(
+            evaluate(&r0, now, &dropping, &control, &thresholds()),
+            RolloutDecision::Rollback { .. }
+        ));
+        control.dropped = 5500; // 110 per agent-hour: within delta
+        assert_eq!(evaluate(&r0, now, &dropping, &control, &thresholds()), RolloutDecision::Soak);
+    }
+}
@@ -0,0 +1,179 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/orchestrator/src/config_rollout_main.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Standalone agent config rollout binary - starts a ring rollout of an agent config version, lists active rollouts, and aborts (rolls back) a rollout.
+
+use std::process;
+use std::time::Duration;
+
+use tracing::{error, info};
+
+#[path = "lib.rs"]
+mod orchestrator;
+
+use orchestrator::config_rollout;
+use orchestrator::db::{CoreDb, DbConfig};
+
+const AGENT_TYPES: &[&str] = &["linux_agent", "windows_agent", "dpi_probe"];
+
+fn usage_and_exit() -> ! {
+    eprintln!("RansomEye Agent Config Rollout");
+    eprintln!("");
+    eprintln!("USAGE:");
+    eprintln!("  ransomeye_config_rollout start --agent-type <type> --config <config.json> --by <operator>");
+    eprintln!("                                 [--rings 5,25,100] [--soak-secs 3600] [--description <text>]");
+    eprintln!("  ransomeye_config_rollout status");
+    eprintln!("  ransomeye_config_rollout abort --agent-type <type> --reason <text>");
+    eprintln!("");
+    eprintln!("NOTES:");
+    eprintln!("  - agent types: linux_agent|windows_agent|dpi_probe; one active rollout per type");
+    eprintln!("  - rings are fleet percentages, strictly increasing and ending at 100");
+    eprintln!("  - the orchestrator promotes each ring after a healthy soak and rolls back on regression");
+    eprintln!("  - abort restores every assigned agent's previous config version");
+    eprintln!("  - DB env vars are required: DB_HOST, DB_PORT, DB_NAME, DB_USER, DB_PASS");
+    process::exit(2);
+}
+
+fn arg_value(name: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter()
+        .position(|a| a == name)
+        .and_then(|i| args.get(i + 1).cloned())
+}
+
+fn agent_type_arg() -> String {
+    match arg_value("--agent-type") {
+        Some(t) if AGENT_TYPES.contains(&t.as_str()) => t,
+        Some(t) => {
+            error!("Invalid --agent-type '{}' (expected one of {})", t, AGENT_TYPES.join(", "));
+            process::exit(2);
+        }
+        None => usage_and_exit(),
+    }
+}
+
+async fn connect() -> CoreDb {
+    let cfg = match DbConfig::from_env_strict() {
+        Ok(c) => c,
+        Err(e) => {
+            error!("{e}");
+            process::exit(1);
+        }
+    };
+    match CoreDb::connect_strict(&cfg).await {
+        Ok(db) => db,
+        Err(e) => {
+            error!("FAIL-CLOSED: DB connect failed: {e}");
+            process::exit(1);
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    orchestrator::BUILD_INFO.handle_version_flag("ransomeye_config_rollout");
+    tracing_subscriber::fmt().with_writer(std::io::stderr).init();
+
+    if std::env::args().any(|a| a == "--help" || a == "-h") {
+        usage_and_exit();
+    }
+    match std::env::args().nth(1).as_deref() {
+        Some("start") => start().await,
+        Some("status") => status().await,
+        Some("abort") => abort().await,
+        _ => usage_and_exit(),
+    }
+}
+
+async fn start() {
+    let agent_type = agent_type_arg();
+    let (Some(config_path), Some(by)) = (arg_value("--config"), arg_value("--by")) else {
+        usage_and_exit();
+    };
+    let rings = match config_rollout::parse_rings(&arg_value("--rings").unwrap_or_else(|| config_rollout::DEFAULT_RINGS.to_string())) {
+        Ok(r) => r,
+        Err(e) => {
+            error!("Invalid --rings: {e}");
+            process::exit(2);
+        }
+    };
+    let soak_secs = match arg_value("--soak-secs").map(|v| v.parse::<u64>()) {
+        None => 3600,
+        Some(Ok(n)) if n > 0 => n,
+        Some(_) => {
+            error!("Invalid --soak-secs (expected integer > 0)");
+            process::exit(2);
+        }
+    };
+    let config: serde_json::Value = match std::fs::read(&config_path).map_err(|e| e.to_string()).and_then(|b| {
+        serde_json::from_slice(&b).map_err(|e| e.to_string())
+    }) {
+        Ok(v @ serde_json::Value::Object(_)) => v,
+        Ok(_) => {
+            error!("Agent config {} must be a JSON object", config_path);
+            process::exit(1);
+        }
+        Err(e) => {
+            error!("Failed to read agent config {}: {}", config_path, e);
+            process::exit(1);
+        }
+    };
+
+    let db = connect().await;
+    let description = arg_value("--description");
+    match config_rollout::start(&db, &agent_type, &config, description.as_deref(), &by, &rings, Duration::from_secs(soak_secs)).await {
+        Ok(rollout_id) => info!(
+            "[CONFIG-ROLLOUT] started rollout_id={} agent_type={} rings={:?} soak_secs={}",
+            rollout_id, agent_type, rings, soak_secs
+        ),
+        Err(e) => {
+            error!("FAIL-CLOSED: {e}");
+            process::exit(1);
+        }
+    }
+}
+
+async fn status() {
+    let db = connect().await;
+    let rollouts = match config_rollout::active_rollouts(&db).await {
+        Ok(r) => r,
+        Err(e) => {
+            error!("FAIL-CLOSED: {e}");
+            process::exit(1);
+        }
+    };
+    if rollouts.is_empty() {
+        info!("[CONFIG-ROLLOUT] no active rollouts");
+    }
+    for r in rollouts {
+        info!(
+            "[CONFIG-ROLLOUT] rollout_id={} agent_type={} version={} ring={}/{} ({}%) ring_started_at={} soak_secs={}",
+            r.rollout_id,
+            r.agent_type,
+            r.config_version_id,
+            r.current_ring + 1,
+            r.rings.len(),
+            r.rings[r.current_ring as usize],
+            r.ring_started_at.to_rfc3339(),
+            r.soak.as_secs()
+        );
+    }
+}
+
+async fn abort() {
+    let agent_type = agent_type_arg();
+    let Some(reason) = arg_value("--reason") else {
+        usage_and_exit();
+    };
+    let db = connect().await;
+    match config_rollout::abort(&db, &agent_type, &reason).await {
+        Ok(Some(rollout_id)) => info!("[CONFIG-ROLLOUT] rolled back rollout_id={} agent_type={}", rollout_id, agent_type),
+        Ok(None) => {
+            error!("No active rollout for {}", agent_type);
+            process::exit(1);
+        }
+        Err(e) => {
+            error!("FAIL-CLOSED: {e}");
+            process::exit(1);
+        }
+    }
+}
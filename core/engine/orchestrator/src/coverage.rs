@@ -0,0 +1,458 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/orchestrator/src/coverage.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Sensor coverage analysis - internal hosts and subnets active in the analysis window without agent or probe visibility, silent enrolled agents and agents without host inventory, stored as a coverage_reports row for dashboards
+
+/*
+ * Coverage Map
+ *
+ * Active hosts are internal addresses seen by any sensor in the analysis window:
+ *
+ *   agent local   local address of a Linux agent network event (linux_agent_telemetry.network_dst_ip)
+ *   agent peer    remote address of a Linux agent network event (network_src_ip)
+ *   probe         endpoint of a DPI flow (dpi_probe_telemetry src_ip / dst_ip)
+ *
+ * A host has agent visibility when an agent reported it as its own address, and probe visibility
+ * when a DPI probe saw its traffic. A host seen only as the peer of an agent has neither: it is
+ * active on the network, but no sensor sees what it does. Hosts are grouped into subnets (/24
+ * IPv4, /64 IPv6 by default): a subnet without any visible host is blind, one with some is partial.
+ *
+ * Enrollment data adds two gaps: active agents that sent no telemetry in the window (silent), and
+ * Linux agents that never reported a host inventory (host facts unknown).
+ */
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt::Write as _;
+use std::net::IpAddr;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use tokio_postgres::types::Json;
+use uuid::Uuid;
+
+use super::db::CoreDb;
+
+/// Blind addresses listed per subnet in the report (counts are always complete)
+const MAX_LISTED_ADDRESSES: usize = 50;
+
+/// RFC 1918, CGNAT and IPv6 unique local ranges
+pub const DEFAULT_INTERNAL_NETWORKS: &[&str] = &["10.0.0.0/8", "172.16.0.0/12", "192.168.0.0/16", "100.64.0.0/10", "fc00::/7"];
+
+/// IP network (shared with the site/zone topology)
+pub use ingest::topology::Cidr;
+
+#[derive(Debug, Clone)]
+pub struct CoverageConfig {
+    /// Activity older than this is not considered
+    pub window: Duration,
+    pub ipv4_prefix: u8,
+    pub ipv6_prefix: u8,
+    /// Only addresses in these networks are hosts of the estate
+    pub internal_networks: Vec<Cidr>,
+}
+
+impl Default for CoverageConfig {
+    fn default() -> Self {
+        Self {
+            window: Duration::hours(24),
+            ipv4_prefix: 24,
+            ipv6_prefix: 64,
+            internal_networks: DEFAULT_INTERNAL_NETWORKS.iter().map(|c| Cidr::parse(c).expect("valid default network")).collect(),
+        }
+    }
+}
+
+/// Addresses seen by sensors in the window
+#[derive(Debug, Clone, Default)]
+pub struct Observations {
+    pub agent_local: BTreeSet<IpAddr>,
+    pub agent_peers: BTreeSet<IpAddr>,
+    pub probe: BTreeSet<IpAddr>,
+    /// Agents with telemetry in the window
+    pub reporting_agents: HashSet<Uuid>,
+}
+
+/// An active (not decommissioned) agents row
+#[derive(Debug, Clone)]
+pub struct EnrolledAgent {
+    pub agent_id: Uuid,
+    pub agent_type: String,
+    /// host_inventory hostname, else the enrolled hostname
+    pub hostname: Option<String>,
+    pub last_seen_at: DateTime<Utc>,
+    pub has_inventory: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubnetStatus {
+    /// No active host of the subnet is visible
+    Blind,
+    Partial,
+    Covered,
+}
+
+impl SubnetStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SubnetStatus::Blind => "blind",
+            SubnetStatus::Partial => "partial",
+            SubnetStatus::Covered => "covered",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SubnetCoverage {
+    pub subnet: String,
+    pub status: SubnetStatus,
+    pub active_hosts: usize,
+    pub agent_hosts: usize,
+    /// Visible to a probe only
+    pub probe_only_hosts: usize,
+    pub blind_hosts: usize,
+    /// First blind addresses (at most 50)
+    pub blind_addresses: Vec<IpAddr>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentGap {
+    pub agent_id: Uuid,
+    pub agent_type: String,
+    pub hostname: Option<String>,
+    pub last_seen_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CoverageReport {
+    pub generated_at: DateTime<Utc>,
+    pub window_start: DateTime<Utc>,
+    pub active_hosts: usize,
+    pub agent_hosts: usize,
+    pub probe_only_hosts: usize,
+    pub blind_hosts: usize,
+    /// Share of active hosts visible to an agent or probe (100 when nothing was active)
+    pub coverage_pct: f64,
+    pub blind_subnets: usize,
+    /// Blind subnets first, then by blind hosts
+    pub subnets: Vec<SubnetCoverage>,
+    pub silent_agents: Vec<AgentGap>,
+    pub agents_without_inventory: Vec<AgentGap>,
+}
+
+impl CoverageReport {
+    pub fn has_gaps(&self) -> bool {
+        self.blind_hosts > 0 || !self.silent_agents.is_empty()
+    }
+
+    pub fn render_text(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "RansomEye Coverage Report ({} to {})", self.window_start.to_rfc3339(), self.generated_at.to_rfc3339());
+        let _ = writeln!(
+            out,
+            "Active hosts: {} | agent: {} | probe only: {} | blind: {} | coverage: {:.1}%",
+            self.active_hosts, self.agent_hosts, self.probe_only_hosts, self.blind_hosts, self.coverage_pct
+        );
+        let _ = writeln!(out, "\nSubnets ({} blind):", self.blind_subnets);
+        for s in self.subnets.iter().filter(|s| s.status != SubnetStatus::Covered) {
+            let _ = writeln!(
+                out,
+                "  {:<20} {:<8} active={} agent={} probe_only={} blind={}",
+                s.subnet, s.status.as_str(), s.active_hosts, s.agent_hosts, s.probe_only_hosts, s.blind_hosts
+            );
+            if !s.blind_addresses.is_empty() {
+                let listed: Vec<String> = s.blind_addresses.iter().map(IpAddr::to_string).collect();
+                let _ = writeln!(out, "      {}", listed.join(" "));
+            }
+        }
+        for (title, gaps) in [("Silent agents", &self.silent_agents), ("Agents without host inventory", &self.agents_without_inventory)] {
+            let _ = writeln!(out, "\n{} ({}):", title, gaps.len());
+            for g in gaps {
+                let _ = writeln!(
+                    out,
+                    "  {} {:<14} {:<30} last seen {}",
+                    g.agent_id,
+                    g.agent_type,
+                    g.hostname.as_deref().unwrap_or("-"),
+                    g.last_seen_at.to_rfc3339()
+                );
+            }
+        }
+        out
+    }
+}
+
+#[derive(Default)]
+struct SubnetTally {
+    active: usize,
+    agent: usize,
+    probe_only: usize,
+    blind: Vec<IpAddr>,
+}
+
+/// Coverage of the observed estate (pure; inputs come from load_observations / load_agents).
+pub fn analyze(
+    observations: &Observations,
+    agents: &[EnrolledAgent],
+    cfg: &CoverageConfig,
+    now: DateTime<Utc>,
+) -> CoverageReport {
+    let internal = |ip: &IpAddr| cfg.internal_networks.iter().any(|n| n.contains(*ip));
+    let active: BTreeSet<IpAddr> = observations
+        .agent_local
+        .iter()
+        .chain(&observations.agent_peers)
+        .chain(&observations.probe)
+        .filter(|ip| internal(ip))
+        .copied()
+        .collect();
+
+    let mut subnets: BTreeMap<Cidr, SubnetTally> = BTreeMap::new();
+    for ip in &active {
+        let prefix = if ip.is_ipv4() { cfg.ipv4_prefix } else { cfg.ipv6_prefix };
+        let tally = subnets.entry(Cidr::of(*ip, prefix)).or_default();
+        tally.active += 1;
+        if observations.agent_local.contains(ip) {
+            tally.agent += 1;
+        } else if observations.probe.contains(ip) {
+            tally.probe_only += 1;
+        } else {
+            tally.blind.push(*ip);
+        }
+    }
+
+    let mut subnets: Vec<SubnetCoverage> = subnets
+        .into_iter()
+        .map(|(cidr, t)| SubnetCoverage {
+            subnet: cidr.to_string(),
+            status: match t.blind.len() {
+                0 => SubnetStatus::Covered,
+                n if n == t.active => SubnetStatus::Blind,
+                _ => SubnetStatus::Partial,
+            },
+            active_hosts: t.active,
+            agent_hosts: t.agent,
+            probe_only_hosts: t.probe_only,
+            blind_hosts: t.blind.len(),
+            blind_addresses: t.blind.into_iter().take(MAX_LISTED_ADDRESSES).collect(),
+        })
+        .collect();
+    subnets.sort_by(|a, b| a.status.cmp(&b.status).then(b.blind_hosts.cmp(&a.blind_hosts)));
+
+    let gap = |a: &EnrolledAgent| AgentGap {
+        agent_id: a.agent_id,
+        agent_type: a.agent_type.clone(),
+        hostname: a.hostname.clone(),
+        last_seen_at: a.last_seen_at,
+    };
+    let silent_agents = agents.iter().filter(|a| !observations.reporting_agents.contains(&a.agent_id)).map(gap).collect();
+    let agents_without_inventory = agents
+        .iter()
+        .filter(|a| a.agent_type == "linux_agent" && !a.has_inventory)
+        .map(gap)
+        .collect();
+
+    let agent_hosts = subnets.iter().map(|s| s.agent_hosts).sum();
+    let probe_only_hosts = subnets.iter().map(|s| s.probe_only_hosts).sum();
+    let blind_hosts = subnets.iter().map(|s| s.blind_hosts).sum::<usize>();
+    CoverageReport {
+        generated_at: now,
+        window_start: now - cfg.window,
+        active_hosts: active.len(),
+        agent_hosts,
+        probe_only_hosts,
+        blind_hosts,
+        coverage_pct: if active.is_empty() { 100.0 } else { 100.0 * (active.len() - blind_hosts) as f64 / active.len() as f64 },
+        blind_subnets: subnets.iter().filter(|s| s.status == SubnetStatus::Blind).count(),
+        subnets,
+        silent_agents,
+        agents_without_inventory,
+    }
+}
+
+async fn distinct_ips(db: &CoreDb, sql: &str, since: DateTime<Utc>, what: &str) -> Result<BTreeSet<IpAddr>, String> {
+    let rows = db
+        .client()
+        .query(sql, &[&since])
+        .await
+        .map_err(|e| format!("FAIL-CLOSED: Cannot read {}: {e}", what))?;
+    Ok(rows.iter().map(|r| r.get::<_, IpAddr>(0)).collect())
+}
+
+/// Addresses and reporting agents since `since`.
+pub async fn load_observations(db: &CoreDb, since: DateTime<Utc>) -> Result<Observations, String> {
+    let agent_local = distinct_ips(
+        db,
+        "SELECT DISTINCT network_dst_ip FROM linux_agent_telemetry WHERE observed_at >= $1 AND network_dst_ip IS NOT NULL",
+        since,
+        "agent local addresses",
+    )
+    .await?;
+    let agent_peers = distinct_ips(
+        db,
+        "SELECT DISTINCT network_src_ip FROM linux_agent_telemetry WHERE observed_at >= $1 AND network_src_ip IS NOT NULL",
+        since,
+        "agent peer addresses",
+    )
+    .await?;
+    let probe = distinct_ips(
+        db,
+        r#"
+        SELECT ip FROM (
+            SELECT src_ip AS ip FROM dpi_probe_telemetry WHERE observed_at >= $1
+            UNION
+            SELECT dst_ip FROM dpi_probe_telemetry WHERE observed_at >= $1
+        ) flows
+        WHERE ip IS NOT NULL
+        "#,
+        since,
+        "DPI flow endpoints",
+    )
+    .await?;
+    let reporting_agents = db
+        .client()
+        .query(
+            r#"
+            SELECT DISTINCT agent_id FROM linux_agent_telemetry WHERE observed_at >= $1
+            UNION
+            SELECT DISTINCT agent_id FROM dpi_probe_telemetry WHERE observed_at >= $1
+            "#,
+            &[&since],
+        )
+        .await
+        .map_err(|e| format!("FAIL-CLOSED: Cannot read reporting agents: {e}"))?
+        .iter()
+        .map(|r| r.get::<_, Uuid>(0))
+        .collect();
+    Ok(Observations { agent_local, agent_peers, probe, reporting_agents })
+}
+
+/// Active, not decommissioned agents with their host_inventory hostname.
+pub async fn load_agents(db: &CoreDb) -> Result<Vec<EnrolledAgent>, String> {
+    let rows = db
+        .client()
+        .query(
+            r#"
+            SELECT a.agent_id, a.agent_type::text, COALESCE(h.hostname, a.host_hostname), a.last_seen_at, h.agent_id IS NOT NULL
+            FROM agents a
+            LEFT JOIN host_inventory h ON h.agent_id = a.agent_id
+            WHERE a.is_active AND a.decommissioned_at IS NULL
+            ORDER BY a.agent_id
+            "#,
+            &[],
+        )
+        .await
+        .map_err(|e| format!("FAIL-CLOSED: Cannot read agents: {e}"))?;
+    Ok(rows
+        .iter()
+        .map(|r| EnrolledAgent {
+            agent_id: r.get(0),
+            agent_type: r.get(1),
+            hostname: r.get(2),
+            last_seen_at: r.get(3),
+            has_inventory: r.get(4),
+        })
+        .collect())
+}
+
+/// Append the report to coverage_reports (the dashboard reads the latest row).
+pub async fn store_report(db: &CoreDb, report: &CoverageReport) -> Result<Uuid, String> {
+    let count = |n: usize| n.min(i32::MAX as usize) as i32;
+    let coverage_report_id = Uuid::new_v4();
+    db.client()
+        .execute(
+            r#"
+            INSERT INTO coverage_reports (coverage_report_id, generated_at, window_start, active_hosts, agent_hosts,
+                                          probe_only_hosts, blind_hosts, blind_subnets, silent_agents, coverage_pct, report)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            "#,
+            &[
+                &coverage_report_id,
+                &report.generated_at,
+                &report.window_start,
+                &count(report.active_hosts),
+                &count(report.agent_hosts),
+                &count(report.probe_only_hosts),
+                &count(report.blind_hosts),
+                &count(report.blind_subnets),
+                &count(report.silent_agents.len()),
+                &report.coverage_pct,
+                &Json(report),
+            ],
+        )
+        .await
+        .map_err(|e| format!("Failed to insert coverage_reports row: {e}"))?;
+    Ok(coverage_report_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn agent(agent_type: &str, has_inventory: bool) -> EnrolledAgent {
+        EnrolledAgent {
+            agent_id: Uuid::new_v4(),
+            agent_type: agent_type.to_string(),
+            hostname: Some("web01".to_string()),
+            last_seen_at: Utc::now(),
+            has_inventory,
+        }
+    }
+
+    #[test]
+    fn cidr_masks_and_validates() {
+        assert_eq!(Cidr::of(ip("10.1.2.3"), 24).to_string(), "10.1.2.0/24");
+        assert_eq!(Cidr::of(ip("2001:db8::1:2"), 64).to_string(), "2001:db8::/64");
+        assert_eq!(Cidr::of(ip("10.1.2.3"), 0).to_string(), "0.0.0.0/0");
+        assert!(Cidr::parse("172.16.0.0/12").unwrap().contains(ip("172.31.255.1")));
+        assert!(!Cidr::parse("172.16.0.0/12").unwrap().contains(ip("172.32.0.1")));
+        assert!(!Cidr::parse("10.0.0.0/8").unwrap().contains(ip("::ffff:a00:1")));
+        assert!(Cidr::parse("10.0.0.1/8").unwrap_err().contains("host bits"));
+        assert!(Cidr::parse("10.0.0.0/33").is_err());
+        assert!(Cidr::parse("10.0.0.0").is_err());
+    }
+
+    #[test]
+    fn classifies_hosts_and_subnets() {
+        let observations = Observations {
+            agent_local: [ip("10.0.1.10")].into(),
+            // 10.0.1.20 and all of 10.0.2.0/24 are only seen as peers; 8.8.8.8 is external
+            agent_peers: [ip("10.0.1.20"), ip("10.0.2.5"), ip("10.0.2.6"), ip("8.8.8.8")].into(),
+            probe: [ip("10.0.1.10"), ip("10.0.3.7"), ip("8.8.8.8")].into(),
+            reporting_agents: HashSet::new(),
+        };
+        let report = analyze(&observations, &[], &CoverageConfig::default(), Utc::now());
+
+        assert_eq!((report.active_hosts, report.agent_hosts, report.probe_only_hosts, report.blind_hosts), (5, 1, 1, 3));
+        assert_eq!(report.coverage_pct, 40.0);
+        assert_eq!(report.blind_subnets, 1);
+        let statuses: Vec<(&str, SubnetStatus)> = report.subnets.iter().map(|s| (s.subnet.as_str(), s.status)).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("10.0.2.0/24", SubnetStatus::Blind),
+                ("10.0.1.0/24", SubnetStatus::Partial),
+                ("10.0.3.0/24", SubnetStatus::Covered),
+            ]
+        );
+        assert_eq!(report.subnets[1].blind_addresses, vec![ip("10.0.1.20")]);
+        assert!(report.has_gaps());
+    }
+
+    #[test]
+    fn reports_silent_agents_and_missing_inventory() {
+        let reporting = agent("linux_agent", false);
+        let silent = agent("dpi_probe", false);
+        let observations = Observations { reporting_agents: [reporting.agent_id].into(), ..Default::default() };
+        let report = analyze(&observations, &[reporting.clone(), silent.clone()], &CoverageConfig::default(), Utc::now());
+
+        assert_eq!(report.coverage_pct, 100.0);
+        assert_eq!(report.silent_agents.iter().map(|g| g.agent_id).collect::<Vec<_>>(), vec![silent.agent_id]);
+        // Only Linux agents report host inventory
+        assert_eq!(report.agents_without_inventory.iter().map(|g| g.agent_id).collect::<Vec<_>>(), vec![reporting.agent_id]);
+        assert!(report.has_gaps());
+    }
+}
@@ -0,0 +1,191 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/orchestrator/src/coverage_main.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Standalone coverage report binary - finds active hosts and subnets without agent or probe visibility, silent agents and agents without host inventory, prints the report (JSON or text) and stores it in coverage_reports; with --attack, reports MITRE ATT&CK technique coverage instead.
+
+use std::collections::BTreeSet;
+use std::process;
+
+use chrono::{Duration, Utc};
+use tracing::{error, info};
+
+#[path = "lib.rs"]
+mod orchestrator;
+
+use orchestrator::attack_coverage;
+use orchestrator::coverage::{self, Cidr, CoverageConfig};
+use orchestrator::db::{CoreDb, DbConfig};
+
+fn usage_and_exit() -> ! {
+    eprintln!("RansomEye Coverage Report");
+    eprintln!("");
+    eprintln!("USAGE:");
+    eprintln!("  ransomeye_coverage_report [--format text|json] [--output <file>] [--window-hours <n>]");
+    eprintln!("                            [--ipv4-prefix <n>] [--ipv6-prefix <n>] [--internal <cidr,...>] [--no-store]");
+    eprintln!("  ransomeye_coverage_report --attack [--all-sensors] [--format text|json] [--output <file>]");
+    eprintln!("");
+    eprintln!("NOTES:");
+    eprintln!("  - Hosts are internal addresses seen by agents or DPI probes within the window (default 24h).");
+    eprintln!("  - --internal replaces the default RFC 1918, CGNAT and fc00::/7 networks.");
+    eprintln!("  - The report is stored in coverage_reports unless --no-store is given.");
+    eprintln!("  - DB env vars are required: DB_HOST, DB_PORT, DB_NAME, DB_USER, DB_PASS");
+    eprintln!("  - Exit code 0 = no gaps, 3 = blind hosts or silent agents found");
+    eprintln!("  - --attack reports which MITRE ATT&CK techniques the current rules are tagged with and the");
+    eprintln!("    enrolled sensor types can observe (--all-sensors: every sensor type, no DB needed). Not stored;");
+    eprintln!("    exit code 0 once the report is written.");
+    process::exit(2);
+}
+
+fn arg_value(name: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter()
+        .position(|a| a == name)
+        .and_then(|i| args.get(i + 1).cloned())
+}
+
+fn write_output(rendered: String) {
+    match arg_value("--output") {
+        Some(path) => {
+            if let Err(e) = std::fs::write(&path, rendered) {
+                error!("Failed to write coverage report to {}: {}", path, e);
+                process::exit(1);
+            }
+        }
+        None => print!("{rendered}"),
+    }
+}
+
+fn render<T: serde::Serialize>(json: bool, report: &T, text: impl FnOnce() -> String) -> String {
+    if !json {
+        return text();
+    }
+    match serde_json::to_string_pretty(report) {
+        Ok(s) => s + "\n",
+        Err(e) => {
+            error!("Failed to serialize coverage report: {e}");
+            process::exit(1);
+        }
+    }
+}
+
+async fn connect_db() -> CoreDb {
+    let db_cfg = match DbConfig::from_env_strict() {
+        Ok(c) => c,
+        Err(e) => {
+            error!("{e}");
+            process::exit(1);
+        }
+    };
+    match CoreDb::connect_strict(&db_cfg).await {
+        Ok(db) => db,
+        Err(e) => {
+            error!("FAIL-CLOSED: DB connect failed: {e}");
+            process::exit(1);
+        }
+    }
+}
+
+/// --attack: technique coverage of the current rules by the enrolled sensor types
+async fn attack_report(json: bool) -> ! {
+    let sensors: BTreeSet<String> = if std::env::args().any(|a| a == "--all-sensors") {
+        ingest::attack::SENSOR_TYPES.iter().map(|s| s.to_string()).collect()
+    } else {
+        let db = connect_db().await;
+        match coverage::load_agents(&db).await {
+            Ok(agents) => agents.into_iter().map(|a| a.agent_type).collect(),
+            Err(e) => {
+                error!("{e}");
+                process::exit(1);
+            }
+        }
+    };
+    let report = attack_coverage::analyze(&attack_coverage::current_rules(), &sensors, Utc::now());
+    write_output(render(json, &report, || report.render_text()));
+    process::exit(0);
+}
+
+fn arg_u8(name: &str, default_value: u8, max: u8) -> u8 {
+    match arg_value(name) {
+        None => default_value,
+        Some(v) => match v.parse::<u8>() {
+            Ok(n) if n <= max => n,
+            _ => usage_and_exit(),
+        },
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    orchestrator::BUILD_INFO.handle_version_flag("ransomeye_coverage_report");
+    tracing_subscriber::fmt().with_writer(std::io::stderr).init();
+
+    if std::env::args().any(|a| a == "--help" || a == "-h") {
+        usage_and_exit();
+    }
+
+    let json = match arg_value("--format").as_deref() {
+        None | Some("text") => false,
+        Some("json") => true,
+        Some(_) => usage_and_exit(),
+    };
+    if std::env::args().any(|a| a == "--attack") {
+        attack_report(json).await;
+    }
+    let defaults = CoverageConfig::default();
+    let window = match arg_value("--window-hours") {
+        None => defaults.window,
+        Some(v) => match v.parse::<i64>() {
+            Ok(n) if (1..=24 * 90).contains(&n) => Duration::hours(n),
+            _ => usage_and_exit(),
+        },
+    };
+    let internal_networks = match arg_value("--internal") {
+        None => defaults.internal_networks,
+        Some(list) => match list.split(',').map(Cidr::parse).collect::<Result<Vec<_>, _>>() {
+            Ok(networks) => networks,
+            Err(e) => {
+                error!("Invalid --internal: {e}");
+                process::exit(2);
+            }
+        },
+    };
+    let cfg = CoverageConfig {
+        window,
+        ipv4_prefix: arg_u8("--ipv4-prefix", defaults.ipv4_prefix, 32),
+        ipv6_prefix: arg_u8("--ipv6-prefix", defaults.ipv6_prefix, 128),
+        internal_networks,
+    };
+    let store = !std::env::args().any(|a| a == "--no-store");
+
+    let db = connect_db().await;
+
+    let now = Utc::now();
+    let observations = match coverage::load_observations(&db, now - cfg.window).await {
+        Ok(o) => o,
+        Err(e) => {
+            error!("{e}");
+            process::exit(1);
+        }
+    };
+    let agents = match coverage::load_agents(&db).await {
+        Ok(a) => a,
+        Err(e) => {
+            error!("{e}");
+            process::exit(1);
+        }
+    };
+
+    let report = coverage::analyze(&observations, &agents, &cfg, now);
+    if store {
+        match coverage::store_report(&db, &report).await {
+            Ok(id) => info!("Coverage report stored | coverage_report_id={} | coverage_pct={:.1}", id, report.coverage_pct),
+            Err(e) => {
+                error!("{e}");
+                process::exit(1);
+            }
+        }
+    }
+
+    write_output(render(json, &report, || report.render_text()));
+
+    process::exit(if report.has_gaps() { 3 } else { 0 });
+}
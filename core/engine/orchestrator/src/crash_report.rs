@@ -0,0 +1,51 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/orchestrator/src/crash_report.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Panic reporting for the orchestrator - records a crash as an error_events row attributed to the registered component
+
+use std::sync::OnceLock;
+
+use kernel::crash::{CrashReport, CrashReporter};
+use uuid::Uuid;
+
+use super::db::{CoreDb, DbConfig};
+
+static COMPONENT_ID: OnceLock<Uuid> = OnceLock::new();
+
+/// Set once startup has registered the component; panics before that are recorded without one.
+pub fn set_component_id(id: Uuid) {
+    let _ = COMPONENT_ID.set(id);
+}
+
+/// Reporter for `kernel::crash::install`. Opens its own connection on a private runtime:
+/// the orchestrator runtime may be the thing that panicked.
+pub fn reporter() -> CrashReporter {
+    Box::new(|report: &CrashReport| {
+        let cfg = DbConfig::from_env_strict()?;
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| format!("crash reporter runtime: {e}"))?;
+        rt.block_on(async {
+            let db = CoreDb::connect_strict(&cfg).await?;
+            let context = serde_json::json!({
+                "service": report.service,
+                "pid": report.pid,
+                "thread": report.thread,
+                "location": report.location,
+                "dump_path": report.dump_path.as_ref().map(|p| p.display().to_string()),
+            });
+            db.insert_error_event(
+                COMPONENT_ID.get().copied(),
+                "critical",
+                "panic",
+                &report.message,
+                Some(&report.backtrace),
+                Some(&context),
+                None,
+                None,
+            )
+            .await
+            .map(|_| ())
+        })
+    })
+}
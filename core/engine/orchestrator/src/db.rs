@@ -7,6 +7,8 @@ use std::fs;
 use std::path::Path;
 
 use chrono::{DateTime, Utc};
+use ingest::storage::postgres::AUDIT_CHAIN_LOCK_KEY;
+use policy::IngestQuota;
 use serde_json::Value as JsonValue;
 use sha2::{Digest, Sha256};
 use tokio_postgres::{Client, NoTls};
@@ -68,6 +70,9 @@ impl DbConfig {
 #[derive(Debug)]
 pub struct CoreDb {
     client: Client,
+    /// In-process guard: the advisory lock is re-entrant per session, so tasks sharing this
+    /// connection must also be serialized locally.
+    audit_chain_lock: tokio::sync::Mutex<()>,
 }
 
 impl CoreDb {
@@ -94,14 +99,29 @@ impl CoreDb {
             .await
             .map_err(|e| format!("Failed to set search_path: {e}"))?;
 
-        Ok(Self { client })
+        Ok(Self {
+            client,
+            audit_chain_lock: tokio::sync::Mutex::new(()),
+        })
     }
 
     pub fn client(&self) -> &Client {
         &self.client
     }
 
+    /// Wait for an in-flight audit chain write, then round-trip the connection. Statements on a
+    /// connection complete in order, so every write issued before this has finished.
+    pub async fn flush(&self) -> Result<(), String> {
+        let _guard = self.audit_chain_lock.lock().await;
+        self.client
+            .query_one("SELECT 1", &[])
+            .await
+            .map_err(|e| format!("Database flush round-trip failed: {e}"))?;
+        Ok(())
+    }
+
     /// Apply the authoritative schema SQL file (idempotent). FAIL-CLOSED if file missing/unreadable or DDL fails.
+    #[tracing::instrument(name = "db.apply_schema", skip_all)]
     pub async fn apply_authoritative_schema_from_env(&self) -> Result<(), String> {
         // Idempotency constraint:
         // The authoritative file contains CREATE TYPE statements WITHOUT IF NOT EXISTS.
@@ -165,6 +185,7 @@ impl CoreDb {
                 schema_sql_path, e
             )
         })?;
+        let contract_sha256 = contract_sha256_hex(sql_raw.as_bytes());
 
         // If schema is not present, apply full authoritative schema (first run).
         if !components_table_exists || !component_type_exists {
@@ -186,6 +207,7 @@ impl CoreDb {
                 .await
                 .map_err(|e| format!("Failed to set search_path after schema apply: {e}"))?;
 
+            self.record_schema_migration(&contract_sha256, "full", &[]).await?;
             return Ok(());
         }
 
@@ -224,8 +246,81 @@ impl CoreDb {
             "error_events",
             // Supporting contract tables required by Core runtime writes
             "components",
+            // Ingest bearer-token authentication (agent enrollment/rotation/revocation)
+            "agent_api_tokens",
+            // Agent signing key pins (historical identity binding, operator-approved rotation)
+            "agent_key_pins",
+            // Duplicate agent-id conflicts and the events quarantined under them
+            "identity_conflicts",
+            "quarantined_events",
+            // Asset criticality tags (detection enrichment)
+            "asset_tags",
+            // Lifecycle webhooks (subscriptions and delivery outbox)
+            "webhook_subscriptions",
+            "webhook_deliveries",
+            "ingest_outbox",
+            // Agent config rollouts (versions, rings, per-agent assignments)
+            "agent_config_versions",
+            "agent_config_rollouts",
+            "agent_config_assignments",
+            // Legal holds (exempt held incidents/entities from retention)
+            "legal_holds",
+            // Detection suppression rules (signed, expiring; matched in the ingest detection transaction)
+            "detection_suppressions",
+            // Maintenance windows (scoped suppress/downgrade; matched in the ingest detection transaction)
+            "maintenance_windows",
+            // Investigation annotations (append-only notes and their revision history)
+            "annotations",
+            "annotation_revisions",
+            // Incident dispositions and per detection rule metrics (refreshed by ingest; rule-tuning report)
+            "incident_closures",
+            "rule_metrics",
+            // Normalization backfills (checkpointed re-normalization runs and rows staged until cutover)
+            "normalization_backfills",
+            "normalized_event_versions",
+            // Triggered DPI packet captures (queued with critical detections, claimed by probes)
+            "pcap_captures",
+            // Operator-requested agent memory acquisitions and their per-chunk hashes
+            "memory_acquisitions",
+            "memory_acquisition_chunks",
+            // Sealed agent log segments (hash-chained lines, Ed25519 seal, chain continuity)
+            "agent_log_segments",
+            // Sandbox detonation submissions and their verdicts
+            "sandbox_submissions",
+            // Signed YARA rule packs and on-demand agent scan requests
+            "yara_rule_packs",
+            "yara_scan_requests",
+            // Offline update bundles applied to this deployment
+            "update_bundles",
+            // Policy-resolved ingestion quotas (published by orchestrator, enforced by ingest)
+            "ingest_component_quotas",
+            // Runtime feature flags (global values and per-tenant overrides, cached by ingest)
+            "feature_flags",
+            // Replay and rate-budget state shared by load-balanced ingest instances
+            "ingest_replay_state",
+            "ingest_replay_nonces",
+            "ingest_rate_windows",
+            // Dropped-event accounting (daily summary and per-run agent counter cursors)
+            "telemetry_drops_daily",
+            "agent_drop_counters",
+            // Host inventory (latest host facts per agent; read by the fleet API)
+            "host_inventory",
+            // Coverage analysis (one row per report run; read by dashboards)
+            "coverage_reports",
+            // Deception effectiveness (per decoy asset rows per analytics run; read by reporting)
+            "deception_effectiveness",
+            // Schema provenance (apply history and validation outcomes; read by the /schema probe)
+            "schema_migrations",
+            "schema_validations",
+            // Rolling-upgrade compatibility (schema generations, dual-written renamed columns)
+            "schema_versions",
+            "schema_column_renames",
             // PROMPT-25/29B: retention policy configuration table is MANDATORY
             "retention_policies",
+            // Retention run history (written by the retention enforcer; runs/status API and CLI)
+            "retention_runs",
+            // Capacity forecast history (written by the orchestrator; /v1/status)
+            "db_size_samples",
         ];
 
         let existing_tables = self
@@ -283,11 +378,61 @@ impl CoreDb {
             .await
             .map_err(|e| format!("Failed to set search_path after incremental apply: {e}"))?;
 
+        self.record_schema_migration(&contract_sha256, "incremental", &missing).await?;
         Ok(())
     }
 
-    /// Validate required tables exist (full contract list) and required columns exist (core-critical tables).
+    async fn record_schema_migration(&self, contract_sha256: &str, kind: &str, tables: &[&str]) -> Result<(), String> {
+        let tables: Vec<String> = tables.iter().map(|t| t.to_string()).collect();
+        self.client
+            .execute(
+                "INSERT INTO ransomeye.schema_migrations (contract_sha256, kind, tables_applied) VALUES ($1, $2, $3)",
+                &[&contract_sha256, &kind, &tables],
+            )
+            .await
+            .map_err(|e| format!("FAIL-CLOSED: Failed to record schema migration ({kind}): {e}"))?;
+        Ok(())
+    }
+
+    /// Validate the schema contract and record the outcome in ransomeye.schema_validations.
+    /// A failed validation is recorded best-effort (the table itself may be what is missing).
     pub async fn validate_schema_contract(&self) -> Result<(), String> {
+        let schema_sql_path = std::env::var("RANSOMEYE_SCHEMA_SQL_PATH").map_err(|_| {
+            "FAIL-CLOSED: RANSOMEYE_SCHEMA_SQL_PATH not set. Must point to the authoritative schema file."
+                .to_string()
+        })?;
+        let schema_bytes = fs::read(&schema_sql_path).map_err(|e| {
+            format!(
+                "FAIL-CLOSED: Failed to read authoritative schema file at {}: {}",
+                schema_sql_path, e
+            )
+        })?;
+        let contract_sha256 = contract_sha256_hex(&schema_bytes);
+
+        let outcome = self.check_schema_contract().await;
+        let (status, detail) = match &outcome {
+            Ok(()) => ("passed", None),
+            Err(e) => ("failed", Some(e.clone())),
+        };
+        let recorded = self
+            .client
+            .execute(
+                "INSERT INTO ransomeye.schema_validations (contract_sha256, status, detail) VALUES ($1, $2, $3)",
+                &[&contract_sha256, &status, &detail],
+            )
+            .await;
+        match (outcome, recorded) {
+            (Ok(()), Err(e)) => Err(format!("FAIL-CLOSED: Failed to record schema validation: {e}")),
+            (Err(e), Err(record_err)) => {
+                error!("Failed to record failed schema validation: {record_err}");
+                Err(e)
+            }
+            (outcome, Ok(_)) => outcome,
+        }
+    }
+
+    /// Validate required tables exist (full contract list) and required columns exist (core-critical tables).
+    async fn check_schema_contract(&self) -> Result<(), String> {
         info!("Validating authoritative DB schema contract...");
 
         // 1) Required tables (PROMPT-21 contract)
@@ -325,8 +470,81 @@ impl CoreDb {
             "error_events",
             // Supporting contract tables required by Core runtime writes
             "components",
+            // Ingest bearer-token authentication (agent enrollment/rotation/revocation)
+            "agent_api_tokens",
+            // Agent signing key pins (historical identity binding, operator-approved rotation)
+            "agent_key_pins",
+            // Duplicate agent-id conflicts and the events quarantined under them
+            "identity_conflicts",
+            "quarantined_events",
+            // Asset criticality tags (detection enrichment)
+            "asset_tags",
+            // Lifecycle webhooks (subscriptions and delivery outbox)
+            "webhook_subscriptions",
+            "webhook_deliveries",
+            "ingest_outbox",
+            // Agent config rollouts (versions, rings, per-agent assignments)
+            "agent_config_versions",
+            "agent_config_rollouts",
+            "agent_config_assignments",
+            // Legal holds (exempt held incidents/entities from retention)
+            "legal_holds",
+            // Detection suppression rules (signed, expiring; matched in the ingest detection transaction)
+            "detection_suppressions",
+            // Maintenance windows (scoped suppress/downgrade; matched in the ingest detection transaction)
+            "maintenance_windows",
+            // Investigation annotations (append-only notes and their revision history)
+            "annotations",
+            "annotation_revisions",
+            // Incident dispositions and per detection rule metrics (refreshed by ingest; rule-tuning report)
+            "incident_closures",
+            "rule_metrics",
+            // Normalization backfills (checkpointed re-normalization runs and rows staged until cutover)
+            "normalization_backfills",
+            "normalized_event_versions",
+            // Triggered DPI packet captures (queued with critical detections, claimed by probes)
+            "pcap_captures",
+            // Operator-requested agent memory acquisitions and their per-chunk hashes
+            "memory_acquisitions",
+            "memory_acquisition_chunks",
+            // Sealed agent log segments (hash-chained lines, Ed25519 seal, chain continuity)
+            "agent_log_segments",
+            // Sandbox detonation submissions and their verdicts
+            "sandbox_submissions",
+            // Signed YARA rule packs and on-demand agent scan requests
+            "yara_rule_packs",
+            "yara_scan_requests",
+            // Offline update bundles applied to this deployment
+            "update_bundles",
+            // Policy-resolved ingestion quotas (published by orchestrator, enforced by ingest)
+            "ingest_component_quotas",
+            // Runtime feature flags (global values and per-tenant overrides, cached by ingest)
+            "feature_flags",
+            // Replay and rate-budget state shared by load-balanced ingest instances
+            "ingest_replay_state",
+            "ingest_replay_nonces",
+            "ingest_rate_windows",
+            // Dropped-event accounting (daily summary and per-run agent counter cursors)
+            "telemetry_drops_daily",
+            "agent_drop_counters",
+            // Host inventory (latest host facts per agent; read by the fleet API)
+            "host_inventory",
+            // Coverage analysis (one row per report run; read by dashboards)
+            "coverage_reports",
+            // Deception effectiveness (per decoy asset rows per analytics run; read by reporting)
+            "deception_effectiveness",
+            // Schema provenance (apply history and validation outcomes; read by the /schema probe)
+            "schema_migrations",
+            "schema_validations",
+            // Rolling-upgrade compatibility (schema generations, dual-written renamed columns)
+            "schema_versions",
+            "schema_column_renames",
             // PROMPT-25/29B: retention policy configuration table is MANDATORY
             "retention_policies",
+            // Retention run history (written by the retention enforcer; runs/status API and CLI)
+            "retention_runs",
+            // Capacity forecast history (written by the orchestrator; /v1/status)
+            "db_size_samples",
         ];
 
         let existing_tables = self
@@ -356,7 +574,7 @@ impl CoreDb {
         }
         if !missing_tables.is_empty() {
             return Err(format!(
-                "FAIL-CLOSED: Authoritative schema validation failed. Missing required tables in schema 'ransomeye': {}",
+                "FAIL-CLOSED: Authoritative schema validation failed. Missing required tables in schema 'ransomeye': {} (run ransomeye_schema_diff for a full report)",
                 missing_tables.join(", ")
             ));
         }
@@ -372,6 +590,7 @@ impl CoreDb {
                     "instance_id",
                     "build_hash",
                     "version",
+                    "build_metadata",
                     "started_at",
                     "last_heartbeat_at",
                     "created_at",
@@ -484,7 +703,7 @@ impl CoreDb {
             }
             if !missing_cols.is_empty() {
                 return Err(format!(
-                    "FAIL-CLOSED: Schema validation failed for table ransomeye.{table}. Missing required columns: {}",
+                    "FAIL-CLOSED: Schema validation failed for table ransomeye.{table}. Missing required columns: {} (run ransomeye_schema_diff for a full report)",
                     missing_cols.join(", ")
                 ));
             }
@@ -495,6 +714,7 @@ impl CoreDb {
     }
 
     /// Upsert the orchestrator into ransomeye.components and return its component_id (FK anchor for core runtime tables).
+    /// `build_metadata` is the embedded build provenance (build_info::BuildInfo::metadata).
     pub async fn upsert_component(
         &self,
         component_type: &str,
@@ -502,23 +722,26 @@ impl CoreDb {
         instance_id: Option<&str>,
         build_hash: Option<&str>,
         version: Option<&str>,
+        build_metadata: Option<&JsonValue>,
     ) -> Result<Uuid, String> {
         let row = self
             .client
             .query_one(
                 r#"
                 INSERT INTO components (
-                    component_type, component_name, instance_id, build_hash, version, started_at, last_heartbeat_at
+                    component_type, component_name, instance_id, build_hash, version, build_metadata,
+                    started_at, last_heartbeat_at
                 )
-                VALUES ($1::text::component_type, $2, $3, $4, $5, NOW(), NOW())
+                VALUES ($1::text::component_type, $2, $3, $4, $5, $6, NOW(), NOW())
                 ON CONFLICT (component_type, component_name, (COALESCE(instance_id, '')))
                 DO UPDATE SET
                     build_hash = COALESCE(EXCLUDED.build_hash, components.build_hash),
                     version = COALESCE(EXCLUDED.version, components.version),
+                    build_metadata = COALESCE(EXCLUDED.build_metadata, components.build_metadata),
                     last_heartbeat_at = NOW()
                 RETURNING component_id
                 "#,
-                &[&component_type, &component_name, &instance_id, &build_hash, &version],
+                &[&component_type, &component_name, &instance_id, &build_hash, &version, &build_metadata],
             )
             .await
             .map_err(|e| format!("Failed to upsert components row: {e}"))?;
@@ -642,7 +865,7 @@ impl CoreDb {
                 r#"
                 SELECT audit_id, chain_hash_sha256, payload_sha256
                 FROM immutable_audit_log
-                ORDER BY created_at DESC
+                ORDER BY created_at DESC, audit_id DESC
                 LIMIT 1
                 "#,
                 &[],
@@ -662,6 +885,50 @@ impl CoreDb {
         }))
     }
 
+    /// Replace ingest_component_quotas with the policy engine's resolved set (one transaction:
+    /// ingest never sees a half-published quota table).
+    pub async fn publish_ingest_quotas(&self, quotas: &[IngestQuota]) -> Result<(), String> {
+        self.client
+            .batch_execute("BEGIN")
+            .await
+            .map_err(|e| format!("Failed to begin ingest quota publish: {e}"))?;
+        let result = async {
+            self.client
+                .execute("DELETE FROM ingest_component_quotas", &[])
+                .await
+                .map_err(|e| format!("Failed to clear ingest_component_quotas: {e}"))?;
+            for q in quotas {
+                let events_per_minute = i64::try_from(q.events_per_minute)
+                    .map_err(|_| format!("Ingest quota for {} out of range: {}", q.component_type, q.events_per_minute))?;
+                self.client
+                    .execute(
+                        r#"
+                        INSERT INTO ingest_component_quotas (component_type, events_per_minute, policy_id, policy_version)
+                        VALUES ($1, $2, $3, $4)
+                        "#,
+                        &[&q.component_type, &events_per_minute, &q.policy_id, &q.policy_version],
+                    )
+                    .await
+                    .map_err(|e| format!("Failed to insert ingest_component_quotas row: {e}"))?;
+            }
+            Ok::<(), String>(())
+        }
+        .await;
+
+        match result {
+            Ok(()) => self
+                .client
+                .batch_execute("COMMIT")
+                .await
+                .map_err(|e| format!("Failed to commit ingest quota publish: {e}")),
+            Err(e) => {
+                let _ = self.client.batch_execute("ROLLBACK").await;
+                Err(e)
+            }
+        }
+    }
+
+    #[tracing::instrument(name = "db.insert_immutable_audit_log", skip_all, fields(action = %action))]
     pub async fn insert_immutable_audit_log(
         &self,
         actor_component_id: Option<Uuid>,
@@ -675,6 +942,48 @@ impl CoreDb {
             .map_err(|e| format!("Failed to serialize audit payload JSON: {e}"))?;
         let payload_sha256 = Self::sha256_bytes(payload_str.as_bytes());
 
+        // Chain extension is read-then-insert; serialize it (in-process mutex + cross-process
+        // transaction-scoped advisory lock) so concurrent writers can never fork the chain.
+        let _guard = self.audit_chain_lock.lock().await;
+        self.client
+            .batch_execute("BEGIN")
+            .await
+            .map_err(|e| format!("Failed to begin audit chain transaction: {e}"))?;
+        let result = async {
+            self.client
+                .execute("SELECT pg_advisory_xact_lock($1)", &[&AUDIT_CHAIN_LOCK_KEY])
+                .await
+                .map_err(|e| format!("Failed to acquire audit chain lock: {e}"))?;
+            self.append_audit_chain_row(actor_component_id, action, object_type, object_id, payload_json, &payload_sha256)
+                .await
+        }
+        .await;
+
+        match result {
+            Ok(audit_id) => {
+                self.client
+                    .batch_execute("COMMIT")
+                    .await
+                    .map_err(|e| format!("Failed to commit audit chain transaction: {e}"))?;
+                Ok(audit_id)
+            }
+            Err(e) => {
+                let _ = self.client.batch_execute("ROLLBACK").await;
+                Err(e)
+            }
+        }
+    }
+
+    /// Read the chain tip and append one row. Caller MUST hold the audit chain locks.
+    async fn append_audit_chain_row(
+        &self,
+        actor_component_id: Option<Uuid>,
+        action: &str,
+        object_type: &str,
+        object_id: Option<Uuid>,
+        payload_json: &JsonValue,
+        payload_sha256: &[u8; 32],
+    ) -> Result<Uuid, String> {
         let (prev_audit_id, prev_payload_sha256, prev_chain_hash) = match self.fetch_last_audit_chain().await? {
             Some((aid, chain_hash, payload_hash)) => (Some(aid), Some(payload_hash), chain_hash),
             None => (None, None, [0u8; 32]),
@@ -683,7 +992,7 @@ impl CoreDb {
         // Chain hash = SHA256(prev_chain_hash || payload_sha256)
         let mut chain_input = Vec::with_capacity(64);
         chain_input.extend_from_slice(&prev_chain_hash);
-        chain_input.extend_from_slice(&payload_sha256);
+        chain_input.extend_from_slice(payload_sha256);
         let chain_hash_sha256 = Self::sha256_bytes(&chain_input);
 
         let payload_sha_vec: Vec<u8> = payload_sha256.to_vec();
@@ -695,11 +1004,11 @@ impl CoreDb {
             .query_one(
                 r#"
                 INSERT INTO immutable_audit_log (
-                    actor_component_id, actor_agent_id, action, object_type, object_id, event_time,
+                    created_at, actor_component_id, actor_agent_id, action, object_type, object_id, event_time,
                     payload_json, payload_sha256, prev_audit_id, prev_payload_sha256, chain_hash_sha256, signature_status
                 )
                 VALUES (
-                    $1, NULL, $2, $3::text::trust_object_type, $4, NOW(),
+                    clock_timestamp(), $1, NULL, $2, $3::text::trust_object_type, $4, NOW(),
                     $5, $6, $7, $8, $9, 'unknown'
                 )
                 RETURNING audit_id
@@ -723,6 +1032,11 @@ impl CoreDb {
     }
 }
 
+/// Contract version: lowercase hex SHA-256 of the authoritative schema file (same value as schema.hash).
+pub fn contract_sha256_hex(schema_bytes: &[u8]) -> String {
+    Sha256::digest(schema_bytes).iter().map(|b| format!("{b:02x}")).collect()
+}
+
 /// Build an incremental schema patch for a set of missing tables using ONLY the authoritative schema source.
 ///
 /// FAIL-CLOSED:
@@ -978,5 +1292,3 @@ fn compile_authoritative_schema_for_postgres(sql: &str) -> String {
 
     out.join("\n")
 }
-
-
@@ -0,0 +1,636 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/orchestrator/src/deception_analytics.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Deception effectiveness analytics - per decoy asset interaction counts, first touch and time-to-first-touch, unique source entities and the detections/incidents that followed, stored as deception_effectiveness rows for reporting
+
+/*
+ * Deception Effectiveness
+ *
+ * Decoy interactions are Linux agent telemetry rows flagged as deception: event_category
+ * 'deception' or 'canary', or a filesystem/network section whose event_type is 'deception'
+ * (honeyfile access and fake service connections from the agent deception engine). The touched
+ * asset is:
+ *
+ *   honeyfile        file_path
+ *   honeycredential  auth_user (honeycred_<id>)
+ *   fake_service     protocol (the service the decoy advertises)
+ *
+ * Interactions naming none of these are counted as unattributed.
+ *
+ * Per asset, over the analysis window (default 30 days), the job reports interactions, first and
+ * last touch, unique sources (agents, users, processes, remote addresses) and the detections
+ * raised on a touching agent within the attribution window (default 24h) after a touch, with the
+ * incidents those detections name (artifacts.incident_id). Suppressed detections raised no alert
+ * and are not counted.
+ *
+ * Deployment times come from an optional asset manifest (--assets). With it, time-to-first-touch
+ * is first touch minus deployment, and deployed assets nobody touched are reported with zero
+ * counts. The first touch is carried over from earlier deception_effectiveness rows, so it is
+ * kept once the window has moved past it.
+ */
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write as _;
+use std::net::IpAddr;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use uuid::Uuid;
+
+use super::db::CoreDb;
+
+/// Longest asset key and name accepted from the manifest
+const MAX_ASSET_TEXT_BYTES: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AssetKind {
+    Honeyfile,
+    Honeycredential,
+    FakeService,
+}
+
+impl AssetKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AssetKind::Honeyfile => "honeyfile",
+            AssetKind::Honeycredential => "honeycredential",
+            AssetKind::FakeService => "fake_service",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "honeyfile" => Some(AssetKind::Honeyfile),
+            "honeycredential" => Some(AssetKind::Honeycredential),
+            "fake_service" => Some(AssetKind::FakeService),
+            _ => None,
+        }
+    }
+}
+
+/// A decoy asset: its kind and the value interactions name it by
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct AssetRef {
+    pub kind: AssetKind,
+    pub key: String,
+}
+
+impl AssetRef {
+    /// The asset a deception telemetry row touched (file path, then honeycredential user, then service)
+    pub fn of(file_path: Option<&str>, auth_user: Option<&str>, protocol: Option<&str>) -> Option<Self> {
+        let present = |v: Option<&str>| v.map(str::trim).filter(|v| !v.is_empty()).map(str::to_string);
+        present(file_path)
+            .map(|key| Self { kind: AssetKind::Honeyfile, key })
+            .or_else(|| present(auth_user).map(|key| Self { kind: AssetKind::Honeycredential, key }))
+            .or_else(|| present(protocol).map(|key| Self { kind: AssetKind::FakeService, key }))
+    }
+}
+
+/// One deception telemetry row
+#[derive(Debug, Clone)]
+pub struct Interaction {
+    pub agent_id: Uuid,
+    pub observed_at: DateTime<Utc>,
+    /// None when the row names no asset
+    pub asset: Option<AssetRef>,
+    /// Username, else uid
+    pub user: Option<String>,
+    pub process: Option<String>,
+    pub source_ip: Option<IpAddr>,
+}
+
+/// A detection that raised an alert on an agent
+#[derive(Debug, Clone)]
+pub struct RaisedDetection {
+    pub detection_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub agent_id: Uuid,
+    pub incident_id: Option<Uuid>,
+}
+
+/// Manifest entry: when a decoy was put in place
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeployedAsset {
+    pub kind: AssetKind,
+    pub key: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    pub deployed_at: DateTime<Utc>,
+}
+
+/// Parse the asset manifest (a JSON array of DeployedAsset); each asset may be listed once.
+pub fn parse_manifest(json: &str) -> Result<Vec<DeployedAsset>, String> {
+    let assets: Vec<DeployedAsset> = serde_json::from_str(json).map_err(|e| format!("invalid asset manifest: {e}"))?;
+    let mut seen = BTreeSet::new();
+    for a in &assets {
+        if a.key.trim().is_empty() || a.key.len() > MAX_ASSET_TEXT_BYTES {
+            return Err(format!("{} asset key must be 1-{} bytes", a.kind.as_str(), MAX_ASSET_TEXT_BYTES));
+        }
+        if a.name.as_ref().is_some_and(|n| n.len() > MAX_ASSET_TEXT_BYTES) {
+            return Err(format!("name of {} '{}' exceeds {} bytes", a.kind.as_str(), a.key, MAX_ASSET_TEXT_BYTES));
+        }
+        if !seen.insert((a.kind, a.key.trim())) {
+            return Err(format!("{} '{}' is listed twice", a.kind.as_str(), a.key));
+        }
+    }
+    Ok(assets)
+}
+
+#[derive(Debug, Clone)]
+pub struct AnalyticsConfig {
+    /// Interactions and detections older than this are not considered
+    pub window: Duration,
+    /// A detection on a touching agent this long after a touch is attributed to the asset
+    pub attribution: Duration,
+}
+
+impl Default for AnalyticsConfig {
+    fn default() -> Self {
+        Self { window: Duration::days(30), attribution: Duration::hours(24) }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AssetEffectiveness {
+    pub kind: AssetKind,
+    pub key: String,
+    pub name: Option<String>,
+    pub deployed_at: Option<DateTime<Utc>>,
+    /// Interactions in the window
+    pub interactions: u64,
+    /// Earliest touch seen by this or an earlier run
+    pub first_touch_at: Option<DateTime<Utc>>,
+    pub last_touch_at: Option<DateTime<Utc>>,
+    /// First touch minus deployment (unknown without a deployment time, or when the first touch predates it)
+    pub time_to_first_touch_secs: Option<i64>,
+    pub unique_agents: usize,
+    pub unique_users: usize,
+    pub unique_processes: usize,
+    pub unique_source_ips: usize,
+    /// Alerting detections on a touching agent within the attribution window after a touch
+    pub detections: usize,
+    /// Distinct incidents named by those detections
+    pub incidents: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EffectivenessReport {
+    pub generated_at: DateTime<Utc>,
+    pub window_start: DateTime<Utc>,
+    pub attribution_secs: i64,
+    pub interactions: u64,
+    /// Deception rows that name no asset
+    pub unattributed_interactions: u64,
+    pub touched_assets: usize,
+    /// Manifest assets without interactions in the window
+    pub untouched_assets: usize,
+    /// Distinct detections attributed to any asset
+    pub detections: usize,
+    pub incidents: usize,
+    /// Most detections first, then most interactions; untouched assets last
+    pub assets: Vec<AssetEffectiveness>,
+}
+
+impl EffectivenessReport {
+    pub fn render_text(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "RansomEye Deception Effectiveness ({} to {})",
+            self.window_start.to_rfc3339(),
+            self.generated_at.to_rfc3339()
+        );
+        let _ = writeln!(
+            out,
+            "Interactions: {} (unattributed: {}) | touched assets: {} | untouched: {} | detections: {} | incidents: {}",
+            self.interactions,
+            self.unattributed_interactions,
+            self.touched_assets,
+            self.untouched_assets,
+            self.detections,
+            self.incidents
+        );
+        let _ = writeln!(out, "\nAssets ({}):", self.assets.len());
+        for a in &self.assets {
+            let _ = writeln!(
+                out,
+                "  {:<15} {:<40} touches={} agents={} users={} processes={} sources={} detections={} incidents={} ttft={}",
+                a.kind.as_str(),
+                a.name.as_deref().unwrap_or(&a.key),
+                a.interactions,
+                a.unique_agents,
+                a.unique_users,
+                a.unique_processes,
+                a.unique_source_ips,
+                a.detections,
+                a.incidents,
+                a.time_to_first_touch_secs.map(|s| format!("{s}s")).unwrap_or_else(|| "-".to_string())
+            );
+        }
+        out
+    }
+}
+
+#[derive(Default)]
+struct AssetTally {
+    interactions: u64,
+    first: Option<DateTime<Utc>>,
+    last: Option<DateTime<Utc>>,
+    /// Touch times per agent (sorted before attribution)
+    touches: HashMap<Uuid, Vec<DateTime<Utc>>>,
+    users: BTreeSet<String>,
+    processes: BTreeSet<String>,
+    source_ips: BTreeSet<IpAddr>,
+}
+
+/// Effectiveness of each asset (pure; inputs come from the load_* functions and the manifest).
+pub fn analyze(
+    interactions: &[Interaction],
+    detections: &[RaisedDetection],
+    deployed: &[DeployedAsset],
+    earlier_first_touch: &HashMap<AssetRef, DateTime<Utc>>,
+    cfg: &AnalyticsConfig,
+    now: DateTime<Utc>,
+) -> EffectivenessReport {
+    let window_start = now - cfg.window;
+    let mut tallies: BTreeMap<AssetRef, AssetTally> = BTreeMap::new();
+    let mut total = 0u64;
+    let mut unattributed = 0u64;
+    for i in interactions.iter().filter(|i| i.observed_at >= window_start && i.observed_at <= now) {
+        total += 1;
+        let Some(asset) = &i.asset else {
+            unattributed += 1;
+            continue;
+        };
+        let t = tallies.entry(asset.clone()).or_default();
+        t.interactions += 1;
+        t.first = Some(t.first.map_or(i.observed_at, |f| f.min(i.observed_at)));
+        t.last = Some(t.last.map_or(i.observed_at, |l| l.max(i.observed_at)));
+        t.touches.entry(i.agent_id).or_default().push(i.observed_at);
+        t.users.extend(i.user.clone());
+        t.processes.extend(i.process.clone());
+        t.source_ips.extend(i.source_ip.filter(|ip| !ip.is_unspecified()));
+    }
+    let touched_assets = tallies.len();
+    let manifest: HashMap<AssetRef, &DeployedAsset> = deployed
+        .iter()
+        .map(|d| (AssetRef { kind: d.kind, key: d.key.trim().to_string() }, d))
+        .collect();
+    for asset in manifest.keys() {
+        tallies.entry(asset.clone()).or_default();
+    }
+
+    let mut by_agent: HashMap<Uuid, Vec<&RaisedDetection>> = HashMap::new();
+    for d in detections.iter().filter(|d| d.created_at >= window_start) {
+        by_agent.entry(d.agent_id).or_default().push(d);
+    }
+
+    let mut all_detections = BTreeSet::new();
+    let mut all_incidents = BTreeSet::new();
+    let mut assets: Vec<AssetEffectiveness> = tallies
+        .into_iter()
+        .map(|(asset, mut t)| {
+            let mut attributed = BTreeSet::new();
+            let mut incidents = BTreeSet::new();
+            for (agent_id, times) in t.touches.iter_mut() {
+                times.sort();
+                for d in by_agent.get(agent_id).into_iter().flatten() {
+                    // Latest touch at or before the detection
+                    let before = times.partition_point(|at| *at <= d.created_at);
+                    if before > 0 && d.created_at - times[before - 1] <= cfg.attribution {
+                        attributed.insert(d.detection_id);
+                        incidents.extend(d.incident_id);
+                    }
+                }
+            }
+            all_detections.extend(attributed.iter().copied());
+            all_incidents.extend(incidents.iter().copied());
+
+            let first_touch_at = match (t.first, earlier_first_touch.get(&asset)) {
+                (Some(f), Some(e)) => Some(f.min(*e)),
+                (f, e) => f.or(e.copied()),
+            };
+            let deployment = manifest.get(&asset);
+            let deployed_at = deployment.map(|d| d.deployed_at);
+            let time_to_first_touch_secs = match (first_touch_at, deployed_at) {
+                (Some(f), Some(d)) if f >= d => Some((f - d).num_seconds()),
+                _ => None,
+            };
+            AssetEffectiveness {
+                name: deployment.and_then(|d| d.name.clone()),
+                deployed_at,
+                interactions: t.interactions,
+                first_touch_at,
+                last_touch_at: t.last,
+                time_to_first_touch_secs,
+                unique_agents: t.touches.len(),
+                unique_users: t.users.len(),
+                unique_processes: t.processes.len(),
+                unique_source_ips: t.source_ips.len(),
+                detections: attributed.len(),
+                incidents: incidents.len(),
+                kind: asset.kind,
+                key: asset.key,
+            }
+        })
+        .collect();
+    assets.sort_by(|a, b| {
+        b.detections
+            .cmp(&a.detections)
+            .then(b.interactions.cmp(&a.interactions))
+            .then_with(|| (a.kind, &a.key).cmp(&(b.kind, &b.key)))
+    });
+
+    EffectivenessReport {
+        generated_at: now,
+        window_start,
+        attribution_secs: cfg.attribution.num_seconds(),
+        interactions: total,
+        unattributed_interactions: unattributed,
+        touched_assets,
+        untouched_assets: assets.iter().filter(|a| a.interactions == 0).count(),
+        detections: all_detections.len(),
+        incidents: all_incidents.len(),
+        assets,
+    }
+}
+
+/// Deception telemetry rows observed since `since`.
+pub async fn load_interactions(db: &CoreDb, since: DateTime<Utc>) -> Result<Vec<Interaction>, String> {
+    let rows = db
+        .client()
+        .query(
+            r#"
+            SELECT agent_id, observed_at, file_path, auth_user, protocol, COALESCE(username, uid::text), process_name, network_src_ip
+            FROM linux_agent_telemetry
+            WHERE observed_at >= $1
+              AND (event_category IN ('deception', 'canary')
+                   OR payload->'filesystem_data'->>'event_type' = 'deception'
+                   OR payload->'network_data'->>'event_type' = 'deception')
+            "#,
+            &[&since],
+        )
+        .await
+        .map_err(|e| format!("FAIL-CLOSED: Cannot read deception interactions: {e}"))?;
+    Ok(rows
+        .iter()
+        .map(|r| Interaction {
+            agent_id: r.get(0),
+            observed_at: r.get(1),
+            asset: AssetRef::of(r.get(2), r.get(3), r.get(4)),
+            user: r.get(5),
+            process: r.get(6),
+            source_ip: r.get(7),
+        })
+        .collect())
+}
+
+/// Agent a detection concerns: artifacts.agent_id, else the source agent of its event.
+fn detection_agent(artifacts: Option<&JsonValue>, event_agent: Option<Uuid>) -> Option<Uuid> {
+    artifacts
+        .and_then(|a| a.get("agent_id")?.as_str())
+        .and_then(|id| Uuid::parse_str(id.trim()).ok())
+        .or(event_agent)
+}
+
+/// Alerting (not suppressed) detections since `since` on any of `agents`.
+pub async fn load_detections(db: &CoreDb, since: DateTime<Utc>, agents: &[Uuid]) -> Result<Vec<RaisedDetection>, String> {
+    if agents.is_empty() {
+        return Ok(Vec::new());
+    }
+    let agent_texts: Vec<String> = agents.iter().map(Uuid::to_string).collect();
+    let rows = db
+        .client()
+        .query(
+            r#"
+            SELECT d.detection_id, d.created_at, d.artifacts, ne.source_agent_id
+            FROM detection_results d
+            LEFT JOIN normalized_events ne ON ne.normalized_event_id = d.normalized_event_id
+            WHERE d.created_at >= $1 AND d.suppression_id IS NULL
+              AND (lower(d.artifacts->>'agent_id') = ANY($2) OR ne.source_agent_id = ANY($3))
+            "#,
+            &[&since, &agent_texts, &agents],
+        )
+        .await
+        .map_err(|e| format!("FAIL-CLOSED: Cannot read detections: {e}"))?;
+    Ok(rows
+        .iter()
+        .filter_map(|r| {
+            let artifacts: Option<JsonValue> = r.get(2);
+            let agent_id = detection_agent(artifacts.as_ref(), r.get(3))?;
+            Some(RaisedDetection {
+                detection_id: r.get(0),
+                created_at: r.get(1),
+                agent_id,
+                incident_id: artifacts
+                    .as_ref()
+                    .and_then(|a| a.get("incident_id")?.as_str())
+                    .and_then(|id| Uuid::parse_str(id.trim()).ok()),
+            })
+        })
+        .collect())
+}
+
+/// Earliest first touch per asset recorded by earlier runs.
+pub async fn load_first_touches(db: &CoreDb) -> Result<HashMap<AssetRef, DateTime<Utc>>, String> {
+    let rows = db
+        .client()
+        .query(
+            r#"
+            SELECT asset_kind, asset_key, min(first_touch_at)
+            FROM deception_effectiveness
+            WHERE first_touch_at IS NOT NULL
+            GROUP BY asset_kind, asset_key
+            "#,
+            &[],
+        )
+        .await
+        .map_err(|e| format!("FAIL-CLOSED: Cannot read earlier first touches: {e}"))?;
+    Ok(rows
+        .iter()
+        .filter_map(|r| {
+            let kind = AssetKind::parse(r.get::<_, &str>(0))?;
+            Some((AssetRef { kind, key: r.get(1) }, r.get(2)))
+        })
+        .collect())
+}
+
+/// Append one deception_effectiveness row per asset for this run (one transaction).
+pub async fn store_report(db: &CoreDb, report: &EffectivenessReport) -> Result<Uuid, String> {
+    let count = |n: usize| n.min(i32::MAX as usize) as i32;
+    let run_id = Uuid::new_v4();
+    let client = db.client();
+    client
+        .batch_execute("BEGIN")
+        .await
+        .map_err(|e| format!("Failed to begin deception_effectiveness insert: {e}"))?;
+    let result = async {
+        for a in &report.assets {
+            client
+                .execute(
+                    r#"
+                    INSERT INTO deception_effectiveness (
+                        deception_run_id, generated_at, window_start, asset_kind, asset_key, asset_name, deployed_at,
+                        interactions, first_touch_at, last_touch_at, time_to_first_touch_secs, unique_agents,
+                        unique_users, unique_processes, unique_source_ips, detections, incidents
+                    )
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)
+                    "#,
+                    &[
+                        &run_id,
+                        &report.generated_at,
+                        &report.window_start,
+                        &a.kind.as_str(),
+                        &a.key,
+                        &a.name,
+                        &a.deployed_at,
+                        &(a.interactions.min(i64::MAX as u64) as i64),
+                        &a.first_touch_at,
+                        &a.last_touch_at,
+                        &a.time_to_first_touch_secs,
+                        &count(a.unique_agents),
+                        &count(a.unique_users),
+                        &count(a.unique_processes),
+                        &count(a.unique_source_ips),
+                        &count(a.detections),
+                        &count(a.incidents),
+                    ],
+                )
+                .await
+                .map_err(|e| format!("Failed to insert deception_effectiveness row: {e}"))?;
+        }
+        Ok::<(), String>(())
+    }
+    .await;
+
+    match result {
+        Ok(()) => client
+            .batch_execute("COMMIT")
+            .await
+            .map(|_| run_id)
+            .map_err(|e| format!("Failed to commit deception_effectiveness rows: {e}")),
+        Err(e) => {
+            let _ = client.batch_execute("ROLLBACK").await;
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(hour: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 5, 1, 0, 0, 0).unwrap() + Duration::hours(hour)
+    }
+
+    fn honeyfile(key: &str) -> AssetRef {
+        AssetRef { kind: AssetKind::Honeyfile, key: key.to_string() }
+    }
+
+    fn touch(agent_id: Uuid, hour: i64, asset: Option<AssetRef>, user: &str, source: Option<&str>) -> Interaction {
+        Interaction {
+            agent_id,
+            observed_at: at(hour),
+            asset,
+            user: Some(user.to_string()),
+            process: Some("cat".to_string()),
+            source_ip: source.map(|s| s.parse().unwrap()),
+        }
+    }
+
+    fn detection(agent_id: Uuid, hour: i64, incident_id: Option<Uuid>) -> RaisedDetection {
+        RaisedDetection { detection_id: Uuid::new_v4(), created_at: at(hour), agent_id, incident_id }
+    }
+
+    #[test]
+    fn classifies_assets_from_telemetry_columns() {
+        assert_eq!(AssetRef::of(Some("/srv/payroll.xlsx"), None, Some("tcp")), Some(honeyfile("/srv/payroll.xlsx")));
+        assert_eq!(
+            AssetRef::of(Some(" "), Some("honeycred_1"), None).map(|a| a.kind),
+            Some(AssetKind::Honeycredential)
+        );
+        assert_eq!(AssetRef::of(None, None, Some("decoy_ssh")).map(|a| a.kind), Some(AssetKind::FakeService));
+        assert_eq!(AssetRef::of(None, None, None), None);
+        assert_eq!(
+            detection_agent(Some(&serde_json::json!({ "agent_id": "not-a-uuid" })), None),
+            None
+        );
+    }
+
+    #[test]
+    fn counts_interactions_sources_and_attributed_detections() {
+        let (web, db_host, other) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let incident = Uuid::new_v4();
+        let interactions = vec![
+            touch(web, 10, Some(honeyfile("/srv/payroll.xlsx")), "www-data", Some("10.0.0.9")),
+            touch(web, 12, Some(honeyfile("/srv/payroll.xlsx")), "www-data", Some("0.0.0.0")),
+            touch(db_host, 20, Some(honeyfile("/srv/payroll.xlsx")), "postgres", None),
+            touch(web, 11, Some(honeyfile("/etc/backup.key")), "root", None),
+            touch(web, 11, None, "root", None),
+        ];
+        let detections = vec![
+            // After the web touches, within 24h: attributed to both assets touched on web
+            detection(web, 30, Some(incident)),
+            // Before any touch on the agent
+            detection(db_host, 19, None),
+            // More than 24h after the last touch on web
+            detection(web, 40, None),
+            detection(db_host, 21, Some(incident)),
+            // Agent that touched nothing
+            detection(other, 21, None),
+        ];
+        let report = analyze(&interactions, &detections, &[], &HashMap::new(), &AnalyticsConfig::default(), at(48));
+
+        assert_eq!((report.interactions, report.unattributed_interactions, report.touched_assets), (5, 1, 2));
+        assert_eq!((report.detections, report.incidents), (2, 1));
+        let payroll = &report.assets[0];
+        assert_eq!(payroll.key, "/srv/payroll.xlsx");
+        assert_eq!((payroll.interactions, payroll.unique_agents, payroll.unique_users), (3, 2, 2));
+        // The unspecified placeholder address is not a source
+        assert_eq!(payroll.unique_source_ips, 1);
+        assert_eq!((payroll.detections, payroll.incidents), (2, 1));
+        assert_eq!((payroll.first_touch_at, payroll.last_touch_at), (Some(at(10)), Some(at(20))));
+        assert_eq!(payroll.time_to_first_touch_secs, None);
+        let key = &report.assets[1];
+        assert_eq!((key.key.as_str(), key.detections), ("/etc/backup.key", 1));
+    }
+
+    #[test]
+    fn manifest_adds_untouched_assets_and_time_to_first_touch() {
+        let manifest = parse_manifest(
+            r#"[
+                {"kind": "honeyfile", "key": "/srv/payroll.xlsx", "name": "Payroll lure", "deployed_at": "2026-05-01T04:00:00Z"},
+                {"kind": "fake_service", "key": "decoy_ssh", "deployed_at": "2026-04-01T00:00:00Z"}
+            ]"#,
+        )
+        .unwrap();
+        let agent = Uuid::new_v4();
+        let interactions = vec![touch(agent, 10, Some(honeyfile("/srv/payroll.xlsx")), "root", None)];
+        let report = analyze(&interactions, &[], &manifest, &HashMap::new(), &AnalyticsConfig::default(), at(48));
+
+        assert_eq!((report.touched_assets, report.untouched_assets), (1, 1));
+        assert_eq!(report.assets[0].name.as_deref(), Some("Payroll lure"));
+        assert_eq!(report.assets[0].time_to_first_touch_secs, Some(6 * 3600));
+        let ssh = &report.assets[1];
+        assert_eq!((ssh.kind, ssh.interactions, ssh.first_touch_at), (AssetKind::FakeService, 0, None));
+
+        // A first touch from an earlier run is kept once the window has moved past it
+        let earlier: HashMap<AssetRef, DateTime<Utc>> = [(honeyfile("/srv/payroll.xlsx"), at(5))].into();
+        let report = analyze(&interactions, &[], &manifest, &earlier, &AnalyticsConfig::default(), at(48));
+        assert_eq!(report.assets[0].first_touch_at, Some(at(5)));
+        assert_eq!(report.assets[0].time_to_first_touch_secs, Some(3600));
+
+        assert!(parse_manifest(r#"[{"kind": "honeyfile", "key": " ", "deployed_at": "2026-05-01T00:00:00Z"}]"#).is_err());
+        assert!(parse_manifest(
+            r#"[{"kind": "honeyfile", "key": "/a", "deployed_at": "2026-05-01T00:00:00Z"},
+                {"kind": "honeyfile", "key": "/a", "deployed_at": "2026-05-02T00:00:00Z"}]"#
+        )
+        .unwrap_err()
+        .contains("twice"));
+        assert!(parse_manifest(r#"[{"kind": "decoy_host", "key": "/a", "deployed_at": "2026-05-01T00:00:00Z"}]"#).is_err());
+    }
+}
@@ -0,0 +1,160 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/orchestrator/src/deception_analytics_main.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Standalone deception analytics binary - computes per decoy asset interactions, time-to-first-touch, unique sources and resulting detections/incidents, prints the report (JSON or text) and stores it in deception_effectiveness.
+
+use std::process;
+
+use chrono::{Duration, Utc};
+use tracing::{error, info};
+
+#[path = "lib.rs"]
+mod orchestrator;
+
+use orchestrator::db::{CoreDb, DbConfig};
+use orchestrator::deception_analytics::{self, AnalyticsConfig};
+
+fn usage_and_exit() -> ! {
+    eprintln!("RansomEye Deception Analytics");
+    eprintln!("");
+    eprintln!("USAGE:");
+    eprintln!("  ransomeye_deception_analytics [--format text|json] [--output <file>] [--assets <manifest.json>]");
+    eprintln!("                                [--window-days <n>] [--attribution-hours <n>] [--no-store]");
+    eprintln!("");
+    eprintln!("NOTES:");
+    eprintln!("  - Interactions are deception telemetry within the window (default 30 days).");
+    eprintln!("  - Detections on a touching agent within --attribution-hours (default 24) after a touch count for the asset.");
+    eprintln!("  - --assets lists deployed decoys (kind, key, name, deployed_at) for time-to-first-touch and untouched assets.");
+    eprintln!("  - The report is stored in deception_effectiveness unless --no-store is given.");
+    eprintln!("  - DB env vars are required: DB_HOST, DB_PORT, DB_NAME, DB_USER, DB_PASS");
+    process::exit(2);
+}
+
+fn arg_value(name: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter()
+        .position(|a| a == name)
+        .and_then(|i| args.get(i + 1).cloned())
+}
+
+fn arg_range(name: &str, default_value: i64, max: i64) -> i64 {
+    match arg_value(name) {
+        None => default_value,
+        Some(v) => match v.parse::<i64>() {
+            Ok(n) if (1..=max).contains(&n) => n,
+            _ => usage_and_exit(),
+        },
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    orchestrator::BUILD_INFO.handle_version_flag("ransomeye_deception_analytics");
+    tracing_subscriber::fmt().with_writer(std::io::stderr).init();
+
+    if std::env::args().any(|a| a == "--help" || a == "-h") {
+        usage_and_exit();
+    }
+
+    let json = match arg_value("--format").as_deref() {
+        None | Some("text") => false,
+        Some("json") => true,
+        Some(_) => usage_and_exit(),
+    };
+    let defaults = AnalyticsConfig::default();
+    let cfg = AnalyticsConfig {
+        window: Duration::days(arg_range("--window-days", defaults.window.num_days(), 365)),
+        attribution: Duration::hours(arg_range("--attribution-hours", defaults.attribution.num_hours(), 24 * 30)),
+    };
+    let deployed = match arg_value("--assets") {
+        None => Vec::new(),
+        Some(path) => match std::fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|s| deception_analytics::parse_manifest(&s)) {
+            Ok(assets) => assets,
+            Err(e) => {
+                error!("Invalid --assets {}: {}", path, e);
+                process::exit(2);
+            }
+        },
+    };
+    let store = !std::env::args().any(|a| a == "--no-store");
+
+    let db_cfg = match DbConfig::from_env_strict() {
+        Ok(c) => c,
+        Err(e) => {
+            error!("{e}");
+            process::exit(1);
+        }
+    };
+
+    let db = match CoreDb::connect_strict(&db_cfg).await {
+        Ok(db) => db,
+        Err(e) => {
+            error!("FAIL-CLOSED: DB connect failed: {e}");
+            process::exit(1);
+        }
+    };
+
+    let now = Utc::now();
+    let since = now - cfg.window;
+    let interactions = match deception_analytics::load_interactions(&db, since).await {
+        Ok(i) => i,
+        Err(e) => {
+            error!("{e}");
+            process::exit(1);
+        }
+    };
+    let mut agents: Vec<_> = interactions.iter().map(|i| i.agent_id).collect();
+    agents.sort();
+    agents.dedup();
+    let detections = match deception_analytics::load_detections(&db, since, &agents).await {
+        Ok(d) => d,
+        Err(e) => {
+            error!("{e}");
+            process::exit(1);
+        }
+    };
+    let earlier_first_touch = match deception_analytics::load_first_touches(&db).await {
+        Ok(f) => f,
+        Err(e) => {
+            error!("{e}");
+            process::exit(1);
+        }
+    };
+
+    let report = deception_analytics::analyze(&interactions, &detections, &deployed, &earlier_first_touch, &cfg, now);
+    if store {
+        match deception_analytics::store_report(&db, &report).await {
+            Ok(id) => info!(
+                "Deception effectiveness stored | deception_run_id={} | assets={} | interactions={}",
+                id,
+                report.assets.len(),
+                report.interactions
+            ),
+            Err(e) => {
+                error!("{e}");
+                process::exit(1);
+            }
+        }
+    }
+
+    let rendered = if json {
+        match serde_json::to_string_pretty(&report) {
+            Ok(s) => s + "\n",
+            Err(e) => {
+                error!("Failed to serialize deception effectiveness report: {e}");
+                process::exit(1);
+            }
+        }
+    } else {
+        report.render_text()
+    };
+
+    match arg_value("--output") {
+        Some(path) => {
+            if let Err(e) = std::fs::write(&path, rendered) {
+                error!("Failed to write deception effectiveness report to {}: {}", path, e);
+                process::exit(1);
+            }
+        }
+        None => print!("{rendered}"),
+    }
+}
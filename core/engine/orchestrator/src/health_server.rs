@@ -0,0 +1,353 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/orchestrator/src/health_server.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Orchestrator health listener for supervisors and load balancers (GET /healthz, /readyz, /state) answered from memory, with the last startup, component_health and audit row ids the orchestrator wrote
+
+/*
+ * Orchestrator Health Listener
+ *
+ * RANSOMEYE_ORCH_HEALTH_ADDR (off by default) serves probes that never touch the database:
+ *   GET /healthz  liveness: 200 unless the orchestrator FAILED or a critical config drift marked
+ *                 it unhealthy (both cleared by a restart)
+ *   GET /readyz   readiness: 200 only when RUNNING with a serving health report, the same verdict
+ *                 as GET /v1/status
+ *   GET /state    lifecycle state, the orchestrator health report, every registered service
+ *                 instance with its last health report, and the ids of the last startup_events,
+ *                 component_health and immutable_audit_log rows the orchestrator wrote
+ *
+ * The probes take no credentials. /state takes the heartbeat bearer token when one is configured,
+ * and a non-loopback listener without a token is refused, as for the status listener.
+ */
+
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Instant;
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::Json,
+    routing::get,
+    Router,
+};
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use serde::Serialize;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use super::service_registry::{OrchestratorStatus, StatusState};
+use super::OrchestratorState;
+
+pub const HEALTHZ_PATH: &str = "/healthz";
+pub const READYZ_PATH: &str = "/readyz";
+pub const STATE_PATH: &str = "/state";
+
+/// Health listener address (RANSOMEYE_ORCH_HEALTH_ADDR); None = disabled (unset or "off").
+///
+/// FAIL-CLOSED: Returns error for an invalid address, or a non-loopback one without a token
+pub fn listen_addr_from_env(token_configured: bool) -> Result<Option<SocketAddr>, String> {
+    let addr = match std::env::var("RANSOMEYE_ORCH_HEALTH_ADDR") {
+        Ok(v) if v.is_empty() || v.eq_ignore_ascii_case("off") => return Ok(None),
+        Ok(v) => v
+            .parse::<SocketAddr>()
+            .map_err(|e| format!("Invalid RANSOMEYE_ORCH_HEALTH_ADDR='{v}': {e}"))?,
+        Err(_) => return Ok(None),
+    };
+    if !token_configured && !addr.ip().is_loopback() {
+        return Err(format!(
+            "FAIL-CLOSED: RANSOMEYE_ORCH_HEALTH_ADDR={addr} is not loopback; set RANSOMEYE_SERVICE_HEARTBEAT_TOKEN_PATH (it guards {STATE_PATH})"
+        ));
+    }
+    Ok(Some(addr))
+}
+
+/// Last row of one audit action written by the orchestrator.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AuditRef {
+    pub audit_id: Uuid,
+    pub at: DateTime<Utc>,
+}
+
+/// Ids of the rows the orchestrator wrote about itself, kept for GET /state.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct AuditTrail {
+    pub startup_event_id: Option<Uuid>,
+    pub startup_health_id: Option<Uuid>,
+    /// Latest component_health row of the orchestrator
+    pub last_health_id: Option<Uuid>,
+    pub last_health_at: Option<DateTime<Utc>>,
+    /// Latest immutable_audit_log row (lite audit log in lite mode)
+    pub last_audit: Option<AuditRef>,
+    /// Latest row per audit action (orchestrator_startup, orchestrator_config_drift, ...)
+    pub last_audit_by_action: BTreeMap<String, AuditRef>,
+}
+
+impl AuditTrail {
+    pub fn record_audit(&mut self, action: &str, audit_id: Uuid, at: DateTime<Utc>) {
+        let entry = AuditRef { audit_id, at };
+        self.last_audit = Some(entry.clone());
+        self.last_audit_by_action.insert(action.to_string(), entry);
+    }
+
+    pub fn record_health(&mut self, health_id: Uuid, at: DateTime<Utc>) {
+        self.last_health_id = Some(health_id);
+        self.last_health_at = Some(at);
+    }
+}
+
+/// GET /healthz and GET /readyz response.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProbeResponse {
+    pub ok: bool,
+    pub state: &'static str,
+    /// Why the probe failed
+    pub detail: Option<String>,
+}
+
+/// GET /state response: the /v1/status body plus the probe verdicts and the audit trail.
+#[derive(Debug, Clone, Serialize)]
+pub struct OrchestratorSnapshot {
+    pub mode: &'static str,
+    pub version: &'static str,
+    pub git_commit: &'static str,
+    pub live: bool,
+    pub ready: bool,
+    #[serde(flatten)]
+    pub status: OrchestratorStatus,
+    pub audit: AuditTrail,
+}
+
+/// Shared with the health listener.
+#[derive(Clone)]
+pub struct HealthState {
+    pub status: StatusState,
+    pub trail: Arc<RwLock<AuditTrail>>,
+    pub mode: &'static str,
+}
+
+/// Liveness verdict: a restart is the remedy for a failed orchestrator or a critical drift.
+pub fn liveness(state: OrchestratorState, config_drift_unhealthy: bool) -> ProbeResponse {
+    let detail = if state == OrchestratorState::Failed {
+        Some("orchestrator failed".to_string())
+    } else if config_drift_unhealthy {
+        Some("critical config drift detected".to_string())
+    } else {
+        None
+    };
+    ProbeResponse { ok: detail.is_none(), state: state.as_str(), detail }
+}
+
+/// Readiness verdict: the orchestrator is RUNNING and its health report is serving.
+pub fn readiness(status: &OrchestratorStatus) -> ProbeResponse {
+    let detail = if status.healthy {
+        None
+    } else {
+        Some(status.health.status_details().unwrap_or_else(|| status.health.status.as_str().to_string()))
+    };
+    ProbeResponse { ok: detail.is_none(), state: status.state, detail }
+}
+
+fn probe(response: ProbeResponse) -> (StatusCode, Json<ProbeResponse>) {
+    let code = if response.ok { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (code, Json(response))
+}
+
+impl HealthState {
+    pub fn snapshot(&self, now: Instant) -> OrchestratorSnapshot {
+        let status = self.status.status(now);
+        let live = liveness(*self.status.current_state.read(), status.config_drift_unhealthy).ok;
+        OrchestratorSnapshot {
+            mode: self.mode,
+            version: super::BUILD_INFO.version,
+            git_commit: super::BUILD_INFO.git_commit,
+            live,
+            ready: readiness(&status).ok,
+            status,
+            audit: self.trail.read().clone(),
+        }
+    }
+}
+
+/// GET /healthz: 200 when live, 503 otherwise (same body).
+async fn handle_healthz(State(state): State<HealthState>) -> (StatusCode, Json<ProbeResponse>) {
+    let drift = state.status.config_drift_unhealthy.load(std::sync::atomic::Ordering::SeqCst);
+    probe(liveness(*state.status.current_state.read(), drift))
+}
+
+/// GET /readyz: 200 when ready, 503 otherwise (same body).
+async fn handle_readyz(State(state): State<HealthState>) -> (StatusCode, Json<ProbeResponse>) {
+    probe(readiness(&state.status.status(Instant::now())))
+}
+
+/// GET /state: always 200 for an authorized caller; the verdicts are in the body.
+async fn handle_state(
+    State(state): State<HealthState>,
+    headers: HeaderMap,
+) -> Result<Json<OrchestratorSnapshot>, StatusCode> {
+    state.status.check_token(&headers)?;
+    Ok(Json(state.snapshot(Instant::now())))
+}
+
+pub fn router(state: HealthState) -> Router {
+    Router::new()
+        .route(HEALTHZ_PATH, get(handle_healthz))
+        .route(READYZ_PATH, get(handle_readyz))
+        .route(STATE_PATH, get(handle_state))
+        .with_state(state)
+}
+
+/// Bind the health listener (FAIL-CLOSED on bind error) and serve it in the background.
+pub async fn serve(addr: SocketAddr, state: HealthState) -> Result<tokio::task::JoinHandle<()>, String> {
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .map_err(|e| format!("Failed to bind health listener {addr}: {e}"))?;
+    info!("Orchestrator health listener on {} ({}, {} and {})", addr, HEALTHZ_PATH, READYZ_PATH, STATE_PATH);
+    let app = router(state);
+    Ok(tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, app).await {
+            error!("Orchestrator health listener stopped: {}", e);
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+    use std::time::Duration;
+
+    use health::HealthStatus;
+    use ingest::service_heartbeat::{Heartbeat, ServiceStatus};
+
+    use super::super::service_registry::ServiceRegistry;
+
+    fn health_state(token: Option<&str>) -> HealthState {
+        let registry = ServiceRegistry::new(vec!["ransomeye_ingestion".to_string()], Duration::from_secs(30));
+        HealthState {
+            status: StatusState {
+                registry: Arc::new(registry),
+                token: token.map(|t| Arc::new(t.to_string())),
+                current_state: Arc::new(RwLock::new(OrchestratorState::ServicesInitialized)),
+                config_drift_unhealthy: Arc::new(AtomicBool::new(false)),
+                capacity: Arc::new(RwLock::new(None)),
+                failed_checks: Arc::new(RwLock::new(Default::default())),
+            },
+            trail: Arc::new(RwLock::new(AuditTrail::default())),
+            mode: "full",
+        }
+    }
+
+    fn ready_heartbeat() -> Heartbeat {
+        Heartbeat {
+            service: "ransomeye_ingestion".to_string(),
+            instance_id: "host-a".to_string(),
+            boot_id: Uuid::new_v4(),
+            version: Some("1.0.0".to_string()),
+            pid: 42,
+            status: ServiceStatus::Ready,
+            interval_secs: 10,
+            sent_at: Utc::now(),
+            details: None,
+            build: None,
+            health: None,
+        }
+    }
+
+    #[test]
+    fn liveness_fails_only_when_a_restart_is_needed() {
+        assert!(liveness(OrchestratorState::Initializing, false).ok);
+        assert!(liveness(OrchestratorState::ShuttingDown, false).ok);
+        let failed = liveness(OrchestratorState::Failed, false);
+        assert!(!failed.ok);
+        assert_eq!(failed.state, "FAILED");
+        assert_eq!(liveness(OrchestratorState::Running, true).detail.as_deref(), Some("critical config drift detected"));
+    }
+
+    #[test]
+    fn readiness_needs_running_and_required_services() {
+        let state = health_state(None);
+        let now = Instant::now();
+        let starting = readiness(&state.status.status(now));
+        assert!(!starting.ok);
+        assert!(starting.detail.unwrap().contains("SERVICES_INITIALIZED"));
+
+        *state.status.current_state.write() = OrchestratorState::Running;
+        let waiting = readiness(&state.status.status(now));
+        assert!(!waiting.ok);
+        assert!(waiting.detail.unwrap().contains("ransomeye_ingestion"));
+
+        state.status.registry.record(&ready_heartbeat(), now, Utc::now());
+        assert!(readiness(&state.status.status(now)).ok);
+        assert!(!readiness(&state.status.status(now + Duration::from_secs(31))).ok);
+    }
+
+    #[test]
+    fn failed_check_degrades_without_unreadying() {
+        let state = health_state(None);
+        let now = Instant::now();
+        *state.status.current_state.write() = OrchestratorState::Running;
+        state.status.registry.record(&ready_heartbeat(), now, Utc::now());
+        state.status.failed_checks.write().insert("db_capacity", "Database write failed: timeout".to_string());
+
+        let status = state.status.status(now);
+        assert_eq!(status.health.status, HealthStatus::Degraded);
+        assert!(status.health.status_details().unwrap().contains("db_capacity_check"));
+        assert!(readiness(&status).ok);
+
+        state.status.failed_checks.write().remove("db_capacity");
+        assert_eq!(state.status.status(now).health.status, HealthStatus::Healthy);
+    }
+
+    #[test]
+    fn audit_trail_keeps_the_latest_row_per_action() {
+        let mut trail = AuditTrail::default();
+        let (first, second, drift) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let t0 = Utc::now();
+        trail.record_audit("orchestrator_service_liveness", first, t0);
+        trail.record_audit("orchestrator_config_drift", drift, t0);
+        trail.record_audit("orchestrator_service_liveness", second, t0);
+        assert_eq!(trail.last_audit.as_ref().map(|a| a.audit_id), Some(second));
+        assert_eq!(trail.last_audit_by_action.len(), 2);
+        assert_eq!(trail.last_audit_by_action["orchestrator_config_drift"].audit_id, drift);
+
+        let health_id = Uuid::new_v4();
+        trail.record_health(health_id, t0);
+        assert_eq!(trail.last_health_id, Some(health_id));
+    }
+
+    #[tokio::test]
+    async fn probes_answer_over_http_and_state_takes_the_token() {
+        let state = health_state(Some("0123456789abcdef-token"));
+        *state.status.current_state.write() = OrchestratorState::Running;
+        let startup_event_id = Uuid::new_v4();
+        state.trail.write().startup_event_id = Some(startup_event_id);
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = router(state.clone());
+        let server = tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let client = reqwest::Client::new();
+        let get = |path: &str| client.get(format!("http://{addr}{path}"));
+
+        assert_eq!(get(HEALTHZ_PATH).send().await.unwrap().status(), 200);
+        // Required service not heartbeating yet
+        let not_ready = get(READYZ_PATH).send().await.unwrap();
+        assert_eq!(not_ready.status(), 503);
+        let body: serde_json::Value = serde_json::from_str(&not_ready.text().await.unwrap()).unwrap();
+        assert_eq!(body["state"], "RUNNING");
+
+        state.status.registry.record(&ready_heartbeat(), Instant::now(), Utc::now());
+        assert_eq!(get(READYZ_PATH).send().await.unwrap().status(), 200);
+
+        assert_eq!(get(STATE_PATH).send().await.unwrap().status(), 401);
+        let state_body = get(STATE_PATH).bearer_auth("0123456789abcdef-token").send().await.unwrap();
+        assert_eq!(state_body.status(), 200);
+        let body: serde_json::Value = serde_json::from_str(&state_body.text().await.unwrap()).unwrap();
+        assert_eq!(body["state"], "RUNNING");
+        assert_eq!(body["ready"], true);
+        assert_eq!(body["services"][0]["service"], "ransomeye_ingestion");
+        assert_eq!(body["audit"]["startup_event_id"], startup_event_id.to_string());
+        server.abort();
+    }
+}
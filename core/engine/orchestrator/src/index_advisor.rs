@@ -0,0 +1,761 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/orchestrator/src/index_advisor.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Read-only index advisor - matches pg_stat_statements predicates and the known query shapes of core services against live indexes and scan statistics, and reports index recommendations with estimated benefit (never applied automatically).
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use super::db::CoreDb;
+
+/// A query shape a core service is known to run, for deployments without pg_stat_statements.
+#[derive(Debug, Clone, Copy)]
+pub struct QueryPattern {
+    pub service: &'static str,
+    pub description: &'static str,
+    pub table: &'static str,
+    pub equality: &'static [&'static str],
+    pub range: &'static [&'static str],
+}
+
+pub const SERVICE_QUERY_PATTERNS: &[QueryPattern] = &[
+    QueryPattern {
+        service: "ingest",
+        description: "agent lookup by producer identity",
+        table: "agents",
+        equality: &["host_hostname", "agent_type"],
+        range: &[],
+    },
+    QueryPattern {
+        service: "ingest outbox relay",
+        description: "claim due messages",
+        table: "ingest_outbox",
+        equality: &["status"],
+        range: &["next_attempt_at"],
+    },
+    QueryPattern {
+        service: "orchestrator webhook dispatcher",
+        description: "claim due deliveries",
+        table: "webhook_deliveries",
+        equality: &["status"],
+        range: &["next_attempt_at"],
+    },
+    QueryPattern {
+        service: "orchestrator config rollout",
+        description: "agent_stats samples since the ring started",
+        table: "linux_agent_telemetry",
+        equality: &["event_category"],
+        range: &["observed_at"],
+    },
+    QueryPattern {
+        service: "orchestrator config rollout",
+        description: "assignments of a rollout",
+        table: "agent_config_assignments",
+        equality: &["rollout_id"],
+        range: &[],
+    },
+    QueryPattern {
+        service: "normalization worker",
+        description: "raw events without a normalized row",
+        table: "normalized_events",
+        equality: &["raw_event_id"],
+        range: &[],
+    },
+    QueryPattern {
+        service: "normalization worker",
+        description: "oldest raw events first",
+        table: "raw_events",
+        equality: &[],
+        range: &["received_at"],
+    },
+    QueryPattern {
+        service: "retention orphan GC",
+        description: "edges of a correlation run",
+        table: "correlation_graph",
+        equality: &["correlation_run_id"],
+        range: &[],
+    },
+    QueryPattern {
+        service: "retention orphan GC",
+        description: "detections of a correlation run",
+        table: "detection_results",
+        equality: &["correlation_run_id"],
+        range: &[],
+    },
+    QueryPattern {
+        service: "retention orphan GC",
+        description: "LLM requests of a correlation run",
+        table: "llm_requests",
+        equality: &["correlation_run_id"],
+        range: &[],
+    },
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TableStats {
+    pub live_rows: i64,
+    pub seq_scan: i64,
+    pub seq_tup_read: i64,
+    pub idx_scan: i64,
+}
+
+impl TableStats {
+    /// Share of scans that read the whole table
+    pub fn seq_scan_share(&self) -> f64 {
+        let total = self.seq_scan + self.idx_scan;
+        if total <= 0 {
+            0.0
+        } else {
+            self.seq_scan as f64 / total as f64
+        }
+    }
+
+    pub fn avg_rows_per_seq_scan(&self) -> i64 {
+        if self.seq_scan <= 0 {
+            0
+        } else {
+            self.seq_tup_read / self.seq_scan
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexInfo {
+    pub name: String,
+    /// None for expression indexes
+    pub leading_column: Option<String>,
+    /// Partial index predicate (pg_get_expr)
+    pub predicate: Option<String>,
+}
+
+/// Live state of the `ransomeye` schema the advisor reasons about.
+#[derive(Debug, Clone, Default)]
+pub struct LiveCatalog {
+    pub columns: BTreeMap<String, BTreeSet<String>>,
+    pub indexes: BTreeMap<String, Vec<IndexInfo>>,
+    pub stats: BTreeMap<String, TableStats>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatementStat {
+    pub query: String,
+    pub calls: i64,
+    pub total_ms: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct AdvisorConfig {
+    /// Tables smaller than this are left to sequential scans
+    pub min_live_rows: i64,
+    /// Most expensive statements read from pg_stat_statements
+    pub top_statements: i64,
+}
+
+impl Default for AdvisorConfig {
+    fn default() -> Self {
+        Self { min_live_rows: 10_000, top_statements: 200 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Impact {
+    Low,
+    Medium,
+    High,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IndexRecommendation {
+    pub table: String,
+    pub columns: Vec<String>,
+    pub impact: Impact,
+    /// Upper bound: statement time spent on this table's shape times its sequential-scan share
+    pub estimated_time_saved_ms: Option<f64>,
+    pub statement_calls: i64,
+    pub seq_scan_share: f64,
+    pub live_rows: i64,
+    pub avg_rows_per_seq_scan: i64,
+    /// Where the shape came from (statement excerpt or service pattern)
+    pub sources: Vec<String>,
+    /// To be reviewed and run by an operator
+    pub create_statement: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IndexAdvisorReport {
+    pub generated_at: DateTime<Utc>,
+    pub pg_stat_statements_available: bool,
+    pub statements_analyzed: usize,
+    pub min_live_rows: i64,
+    pub recommendations: Vec<IndexRecommendation>,
+    pub notes: Vec<String>,
+}
+
+impl IndexAdvisorReport {
+    pub fn render_text(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "Index advisor report ({})", self.generated_at.to_rfc3339());
+        if self.pg_stat_statements_available {
+            let _ = writeln!(out, "pg_stat_statements: {} statement(s) analyzed", self.statements_analyzed);
+        } else {
+            let _ = writeln!(out, "pg_stat_statements: not available (service query patterns only)");
+        }
+        for note in &self.notes {
+            let _ = writeln!(out, "Note: {}", note);
+        }
+        if self.recommendations.is_empty() {
+            let _ = writeln!(out, "No missing indexes found (tables under {} rows are not considered).", self.min_live_rows);
+            return out;
+        }
+        let _ = writeln!(out, "Recommendations ({}):", self.recommendations.len());
+        for (i, r) in self.recommendations.iter().enumerate() {
+            let _ = writeln!(out, "{}. [{:?}] {} ({})", i + 1, r.impact, r.table, r.columns.join(", "));
+            if let Some(ms) = r.estimated_time_saved_ms {
+                let _ = writeln!(out, "   estimated time saved: up to {:.0} ms over {} call(s)", ms, r.statement_calls);
+            }
+            let _ = writeln!(
+                out,
+                "   {} live rows, {:.0}% sequential scans, ~{} rows read per sequential scan",
+                r.live_rows,
+                r.seq_scan_share * 100.0,
+                r.avg_rows_per_seq_scan
+            );
+            for s in &r.sources {
+                let _ = writeln!(out, "   source: {}", s);
+            }
+            let _ = writeln!(out, "   {}", r.create_statement);
+        }
+        let _ = writeln!(out, "Recommendations are advisory: review them and apply manually.");
+        out
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PredicateKind {
+    Equality,
+    Range,
+}
+
+const SQL_KEYWORDS: &[&str] = &[
+    "select", "from", "where", "join", "left", "right", "inner", "outer", "full", "cross", "on", "using", "group",
+    "order", "by", "limit", "offset", "set", "returning", "values", "union", "having", "for", "lateral", "natural",
+    "as", "and", "or", "not", "in", "is", "null", "between", "exists", "case", "when", "then", "else", "end",
+    "update", "delete", "insert", "into", "with", "do", "conflict", "skip", "locked", "nowait", "asc", "desc",
+];
+
+fn is_keyword(tok: &str) -> bool {
+    SQL_KEYWORDS.contains(&tok)
+}
+
+fn is_identifier(tok: &str) -> bool {
+    tok.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_') && !is_keyword(tok)
+}
+
+/// Lower-cased tokens; string literals collapse to `'`, quotes around identifiers are dropped.
+fn tokenize(sql: &str) -> Vec<String> {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '\'' {
+            let mut j = i + 1;
+            while j < chars.len() {
+                if chars[j] == '\'' {
+                    if chars.get(j + 1) == Some(&'\'') {
+                        j += 2;
+                        continue;
+                    }
+                    break;
+                }
+                j += 1;
+            }
+            out.push("'".to_string());
+            i = j + 1;
+        } else if c.is_alphanumeric() || matches!(c, '_' | '"' | '$' | '.') {
+            let mut tok = String::new();
+            while i < chars.len() && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '"' | '$' | '.')) {
+                if chars[i] != '"' {
+                    tok.push(chars[i].to_ascii_lowercase());
+                }
+                i += 1;
+            }
+            out.push(tok);
+        } else if matches!(c, '<' | '>' | '=' | '!') {
+            let mut tok = String::new();
+            while i < chars.len() && matches!(chars[i], '<' | '>' | '=' | '!') {
+                tok.push(chars[i]);
+                i += 1;
+            }
+            out.push(tok);
+        } else if c == ':' && chars.get(i + 1) == Some(&':') {
+            out.push("::".to_string());
+            i += 2;
+        } else {
+            out.push(c.to_string());
+            i += 1;
+        }
+    }
+    out
+}
+
+fn unqualified(name: &str) -> &str {
+    name.rsplit('.').next().unwrap_or(name)
+}
+
+/// (table, alias) for every FROM / JOIN / UPDATE target that is a plain relation.
+fn table_refs(tokens: &[String]) -> Vec<(String, Option<String>)> {
+    let mut refs = Vec::new();
+    for (i, tok) in tokens.iter().enumerate() {
+        if !matches!(tok.as_str(), "from" | "join" | "update") {
+            continue;
+        }
+        let Some(name) = tokens.get(i + 1).filter(|t| is_identifier(t)) else {
+            continue;
+        };
+        let mut j = i + 2;
+        if tokens.get(j).map(String::as_str) == Some("as") {
+            j += 1;
+        }
+        let alias = tokens.get(j).filter(|t| is_identifier(t) && !t.contains('.')).cloned();
+        refs.push((unqualified(name).to_string(), alias));
+    }
+    refs
+}
+
+/// (qualifier, column, kind) for every `column <op> ...` outside SET lists.
+fn predicates(tokens: &[String]) -> Vec<(Option<String>, String, PredicateKind)> {
+    let mut out = Vec::new();
+    let mut in_set = false;
+    for i in 0..tokens.len().saturating_sub(1) {
+        let tok = tokens[i].as_str();
+        match tok {
+            "set" => {
+                in_set = true;
+                continue;
+            }
+            "where" | "from" | "returning" => in_set = false,
+            _ => {}
+        }
+        if in_set || !is_identifier(tok) || (i > 0 && tokens[i - 1] == "::") {
+            continue;
+        }
+        let kind = match tokens[i + 1].as_str() {
+            "=" | "in" | "is" => PredicateKind::Equality,
+            "<" | ">" | "<=" | ">=" | "between" => PredicateKind::Range,
+            _ => continue,
+        };
+        let mut push = |t: &str| {
+            let (qualifier, column) = match t.rsplit_once('.') {
+                Some((q, c)) => (Some(unqualified(q).to_string()), c.to_string()),
+                None => (None, t.to_string()),
+            };
+            out.push((qualifier, column, kind));
+        };
+        push(tok);
+        // Join condition: the other side is a column too
+        if kind == PredicateKind::Equality {
+            if let Some(rhs) = tokens.get(i + 2).filter(|t| is_identifier(t) && t.contains('.')) {
+                push(rhs);
+            }
+        }
+    }
+    out
+}
+
+/// Index shapes (table -> columns: equality columns sorted, then the first range column) a
+/// statement would benefit from. Columns are resolved against the live catalog; unknown names
+/// (functions, CTEs, other schemas) are dropped.
+pub fn statement_shapes(sql: &str, catalog: &LiveCatalog) -> BTreeMap<String, Vec<String>> {
+    let tokens = tokenize(sql);
+    let refs = table_refs(&tokens);
+    let mut eq: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    let mut range: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    for (qualifier, column, kind) in predicates(&tokens) {
+        let candidates: Vec<&String> = match &qualifier {
+            Some(q) => refs
+                .iter()
+                .filter(|(t, a)| a.as_deref() == Some(q.as_str()) || t == q)
+                .map(|(t, _)| t)
+                .collect(),
+            None => refs.iter().map(|(t, _)| t).collect(),
+        };
+        let owners: BTreeSet<&String> = candidates
+            .into_iter()
+            .filter(|t| catalog.columns.get(*t).is_some_and(|cols| cols.contains(&column)))
+            .collect();
+        if owners.len() != 1 {
+            continue;
+        }
+        let table = owners.into_iter().next().unwrap().clone();
+        match kind {
+            PredicateKind::Equality => {
+                eq.entry(table).or_default().insert(column);
+            }
+            PredicateKind::Range => range.entry(table).or_default().push(column),
+        }
+    }
+
+    let tables: BTreeSet<String> = eq.keys().chain(range.keys()).cloned().collect();
+    tables
+        .into_iter()
+        .map(|table| {
+            let mut cols: Vec<String> = eq.get(&table).map(|s| s.iter().cloned().collect()).unwrap_or_default();
+            if let Some(r) = range.get(&table).and_then(|r| r.iter().find(|c| !cols.contains(c))) {
+                cols.push(r.clone());
+            }
+            cols.truncate(3);
+            (table, cols)
+        })
+        .collect()
+}
+
+/// An existing index serves the shape when it leads with one of its columns, or when it is a
+/// partial index whose predicate already pins every column of the shape.
+pub fn is_covered(columns: &[String], indexes: &[IndexInfo]) -> bool {
+    indexes.iter().any(|idx| {
+        idx.leading_column.as_ref().is_some_and(|c| columns.contains(c))
+            || idx.predicate.as_deref().is_some_and(|p| {
+                let tokens = tokenize(p);
+                columns.iter().all(|c| tokens.iter().any(|t| unqualified(t) == c))
+            })
+    })
+}
+
+pub fn rate(stats: &TableStats, time_saved_share: Option<f64>) -> Impact {
+    let seq_share = stats.seq_scan_share();
+    if time_saved_share.is_some_and(|s| s >= 0.10) || (seq_share >= 0.5 && stats.live_rows >= 1_000_000) {
+        Impact::High
+    } else if time_saved_share.is_some_and(|s| s >= 0.01) || seq_share >= 0.2 || stats.live_rows >= 100_000 {
+        Impact::Medium
+    } else {
+        Impact::Low
+    }
+}
+
+pub fn create_statement(table: &str, columns: &[String]) -> String {
+    let mut name = format!("idx_{}_{}_advised", table, columns.join("_"));
+    // PostgreSQL identifier limit
+    name.truncate(63);
+    format!(
+        "CREATE INDEX CONCURRENTLY IF NOT EXISTS {} ON ransomeye.{} ({});",
+        name,
+        table,
+        columns.join(", ")
+    )
+}
+
+fn excerpt(query: &str) -> String {
+    let flat = query.split_whitespace().collect::<Vec<_>>().join(" ");
+    if flat.chars().count() > 120 {
+        format!("{}...", flat.chars().take(117).collect::<String>())
+    } else {
+        flat
+    }
+}
+
+#[derive(Default)]
+struct Candidate {
+    total_ms: f64,
+    calls: i64,
+    sources: Vec<String>,
+}
+
+/// Build the report. Pure: all inputs are loaded beforehand.
+pub fn advise(
+    catalog: &LiveCatalog,
+    statements: Option<&[StatementStat]>,
+    patterns: &[QueryPattern],
+    cfg: &AdvisorConfig,
+    generated_at: DateTime<Utc>,
+) -> IndexAdvisorReport {
+    let mut candidates: BTreeMap<(String, Vec<String>), Candidate> = BTreeMap::new();
+    let total_ms: f64 = statements.map(|s| s.iter().map(|st| st.total_ms).sum()).unwrap_or(0.0);
+
+    for st in statements.unwrap_or_default() {
+        for (table, cols) in statement_shapes(&st.query, catalog) {
+            let c = candidates.entry((table, cols)).or_default();
+            c.total_ms += st.total_ms;
+            c.calls += st.calls;
+            c.sources.push(format!("pg_stat_statements ({} calls): {}", st.calls, excerpt(&st.query)));
+        }
+    }
+    for p in patterns {
+        let mut cols: Vec<String> = p.equality.iter().map(|c| c.to_string()).collect();
+        cols.sort();
+        cols.extend(p.range.iter().take(1).map(|c| c.to_string()));
+        let c = candidates.entry((p.table.to_string(), cols)).or_default();
+        c.sources.push(format!("{}: {}", p.service, p.description));
+    }
+
+    let mut notes = Vec::new();
+    let mut recommendations = Vec::new();
+    for ((table, columns), c) in candidates {
+        if columns.is_empty() {
+            continue;
+        }
+        let Some(table_cols) = catalog.columns.get(&table) else {
+            notes.push(format!("{} not present in this deployment", table));
+            continue;
+        };
+        if columns.iter().any(|col| !table_cols.contains(col)) {
+            continue;
+        }
+        let stats = catalog.stats.get(&table).copied().unwrap_or_default();
+        if stats.live_rows < cfg.min_live_rows {
+            continue;
+        }
+        if is_covered(&columns, catalog.indexes.get(&table).map(Vec::as_slice).unwrap_or_default()) {
+            continue;
+        }
+        let estimated = (c.total_ms > 0.0).then(|| c.total_ms * stats.seq_scan_share());
+        let share = estimated.filter(|_| total_ms > 0.0).map(|ms| ms / total_ms);
+        recommendations.push(IndexRecommendation {
+            create_statement: create_statement(&table, &columns),
+            impact: rate(&stats, share),
+            estimated_time_saved_ms: estimated,
+            statement_calls: c.calls,
+            seq_scan_share: stats.seq_scan_share(),
+            live_rows: stats.live_rows,
+            avg_rows_per_seq_scan: stats.avg_rows_per_seq_scan(),
+            sources: c.sources,
+            table,
+            columns,
+        });
+    }
+    recommendations.sort_by(|a, b| {
+        b.impact
+            .cmp(&a.impact)
+            .then(b.estimated_time_saved_ms.unwrap_or(0.0).total_cmp(&a.estimated_time_saved_ms.unwrap_or(0.0)))
+            .then(b.live_rows.cmp(&a.live_rows))
+    });
+    notes.sort();
+    notes.dedup();
+
+    IndexAdvisorReport {
+        generated_at,
+        pg_stat_statements_available: statements.is_some(),
+        statements_analyzed: statements.map(<[StatementStat]>::len).unwrap_or(0),
+        min_live_rows: cfg.min_live_rows,
+        recommendations,
+        notes,
+    }
+}
+
+/// Columns, index leading columns / predicates, and scan statistics of the `ransomeye` schema.
+pub async fn load_live_catalog(db: &CoreDb) -> Result<LiveCatalog, String> {
+    let mut catalog = LiveCatalog::default();
+
+    let rows = db
+        .client()
+        .query(
+            r#"
+            SELECT c.relname::text, a.attname::text
+            FROM pg_attribute a
+            JOIN pg_class c ON c.oid = a.attrelid
+            JOIN pg_namespace n ON n.oid = c.relnamespace
+            WHERE n.nspname = 'ransomeye' AND c.relkind IN ('r', 'p') AND a.attnum > 0 AND NOT a.attisdropped
+            "#,
+            &[],
+        )
+        .await
+        .map_err(|e| format!("Index advisor failed reading live columns: {e}"))?;
+    for r in rows {
+        catalog.columns.entry(r.get(0)).or_default().insert(r.get(1));
+    }
+
+    let rows = db
+        .client()
+        .query(
+            r#"
+            SELECT t.relname::text, ic.relname::text, a.attname::text, pg_get_expr(i.indpred, i.indrelid)
+            FROM pg_index i
+            JOIN pg_class t ON t.oid = i.indrelid
+            JOIN pg_class ic ON ic.oid = i.indexrelid
+            JOIN pg_namespace n ON n.oid = t.relnamespace
+            LEFT JOIN pg_attribute a ON a.attrelid = t.oid AND a.attnum = i.indkey[0]
+            WHERE n.nspname = 'ransomeye'
+            "#,
+            &[],
+        )
+        .await
+        .map_err(|e| format!("Index advisor failed reading live indexes: {e}"))?;
+    for r in rows {
+        catalog.indexes.entry(r.get(0)).or_default().push(IndexInfo {
+            name: r.get(1),
+            leading_column: r.get(2),
+            predicate: r.get(3),
+        });
+    }
+
+    let rows = db
+        .client()
+        .query(
+            r#"
+            SELECT relname::text, n_live_tup, COALESCE(seq_scan, 0), COALESCE(seq_tup_read, 0), COALESCE(idx_scan, 0)
+            FROM pg_stat_user_tables
+            WHERE schemaname = 'ransomeye'
+            "#,
+            &[],
+        )
+        .await
+        .map_err(|e| format!("Index advisor failed reading table statistics: {e}"))?;
+    for r in rows {
+        catalog.stats.insert(
+            r.get(0),
+            TableStats { live_rows: r.get(1), seq_scan: r.get(2), seq_tup_read: r.get(3), idx_scan: r.get(4) },
+        );
+    }
+
+    Ok(catalog)
+}
+
+/// Most expensive statements of the current database, or None when pg_stat_statements is not
+/// installed. Statements hidden from this role (`<insufficient privilege>`) are skipped.
+pub async fn load_statements(db: &CoreDb, limit: i64) -> Result<Option<Vec<StatementStat>>, String> {
+    let row = db
+        .client()
+        .query_one("SELECT EXISTS (SELECT 1 FROM pg_extension WHERE extname = 'pg_stat_statements')", &[])
+        .await
+        .map_err(|e| format!("Index advisor failed checking for pg_stat_statements: {e}"))?;
+    if !row.get::<_, bool>(0) {
+        return Ok(None);
+    }
+
+    // total_exec_time since PostgreSQL 13, total_time before
+    let row = db
+        .client()
+        .query_one(
+            r#"
+            SELECT EXISTS (
+              SELECT 1 FROM pg_attribute
+              WHERE attrelid = 'pg_stat_statements'::regclass AND attname = 'total_exec_time'
+            )
+            "#,
+            &[],
+        )
+        .await
+        .map_err(|e| format!("Index advisor failed reading pg_stat_statements columns: {e}"))?;
+    let time_col = if row.get::<_, bool>(0) { "total_exec_time" } else { "total_time" };
+
+    let rows = db
+        .client()
+        .query(
+            &format!(
+                r#"
+                SELECT query, calls::bigint, {time_col}::float8
+                FROM pg_stat_statements
+                WHERE dbid = (SELECT oid FROM pg_database WHERE datname = current_database())
+                  AND query NOT LIKE '<insufficient privilege>%'
+                ORDER BY {time_col} DESC
+                LIMIT $1
+                "#
+            ),
+            &[&limit],
+        )
+        .await
+        .map_err(|e| format!("Index advisor failed reading pg_stat_statements: {e}"))?;
+    Ok(Some(
+        rows.iter()
+            .map(|r| StatementStat { query: r.get(0), calls: r.get(1), total_ms: r.get(2) })
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn catalog() -> LiveCatalog {
+        let mut c = LiveCatalog::default();
+        let cols = |names: &[&str]| names.iter().map(|s| s.to_string()).collect::<BTreeSet<_>>();
+        c.columns.insert("linux_agent_telemetry".into(), cols(&["telemetry_id", "agent_id", "event_category", "observed_at"]));
+        c.columns.insert("agents".into(), cols(&["agent_id", "agent_type", "host_hostname"]));
+        c.columns.insert("ingest_outbox".into(), cols(&["outbox_id", "status", "next_attempt_at"]));
+        c.indexes.insert(
+            "linux_agent_telemetry".into(),
+            vec![IndexInfo { name: "idx_linux_agent_telemetry_observed_at".into(), leading_column: Some("observed_at".into()), predicate: None }],
+        );
+        c.indexes.insert(
+            "ingest_outbox".into(),
+            vec![IndexInfo {
+                name: "idx_ingest_outbox_due".into(),
+                leading_column: Some("next_attempt_at".into()),
+                predicate: Some("(status = 'pending'::text)".into()),
+            }],
+        );
+        let big = TableStats { live_rows: 5_000_000, seq_scan: 90, seq_tup_read: 450_000_000, idx_scan: 10 };
+        c.stats.insert("linux_agent_telemetry".into(), big);
+        c.stats.insert("agents".into(), TableStats { live_rows: 50_000, seq_scan: 10, seq_tup_read: 500_000, idx_scan: 90 });
+        c.stats.insert("ingest_outbox".into(), big);
+        c
+    }
+
+    #[test]
+    fn shapes_resolve_aliases_and_skip_set_lists() {
+        let c = catalog();
+        let sql = "SELECT t.agent_id FROM linux_agent_telemetry t JOIN agents a ON a.agent_id = t.agent_id \
+                   WHERE t.event_category = 'agent_stats' AND t.observed_at >= $1 AND a.agent_type::text = $3";
+        let shapes = statement_shapes(sql, &c);
+        assert_eq!(shapes["linux_agent_telemetry"], vec!["agent_id", "event_category", "observed_at"]);
+        assert_eq!(shapes["agents"], vec!["agent_id"]);
+
+        let shapes = statement_shapes("UPDATE agents SET agent_type = $2 WHERE host_hostname = $1", &c);
+        assert_eq!(shapes["agents"], vec!["host_hostname"]);
+    }
+
+    #[test]
+    fn partial_index_predicate_covers_pinned_columns() {
+        let c = catalog();
+        let cols = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert!(is_covered(&cols(&["status", "next_attempt_at"]), &c.indexes["ingest_outbox"]));
+        assert!(is_covered(&cols(&["status"]), &c.indexes["ingest_outbox"]));
+        assert!(!is_covered(&cols(&["outbox_id"]), &c.indexes["ingest_outbox"]));
+    }
+
+    #[test]
+    fn advise_recommends_uncovered_shapes_on_large_tables() {
+        let c = catalog();
+        let statements = vec![StatementStat {
+            query: "SELECT * FROM linux_agent_telemetry WHERE event_category = $1".into(),
+            calls: 400,
+            total_ms: 9_000.0,
+        }];
+        let report = advise(&c, Some(&statements), SERVICE_QUERY_PATTERNS, &AdvisorConfig::default(), Utc::now());
+
+        let rec = &report.recommendations[0];
+        assert_eq!(rec.table, "linux_agent_telemetry");
+        assert_eq!(rec.columns, vec!["event_category"]);
+        assert_eq!(rec.impact, Impact::High);
+        assert_eq!(rec.estimated_time_saved_ms, Some(9_000.0 * 0.9));
+        assert_eq!(
+            rec.create_statement,
+            "CREATE INDEX CONCURRENTLY IF NOT EXISTS idx_linux_agent_telemetry_event_category_advised ON ransomeye.linux_agent_telemetry (event_category);"
+        );
+
+        // The rollout pattern leads with event_category too but observed_at is already indexed as a
+        // leading column, so only the statement shape is reported for this table
+        assert_eq!(report.recommendations.iter().filter(|r| r.table == "linux_agent_telemetry").count(), 1);
+        // Agent lookup: 50k rows, uncovered
+        assert!(report.recommendations.iter().any(|r| r.table == "agents" && r.columns == vec!["agent_type", "host_hostname"]));
+        // Outbox claim is covered by the partial index
+        assert!(!report.recommendations.iter().any(|r| r.table == "ingest_outbox"));
+        // Pattern tables missing from the catalog are noted, not recommended
+        assert!(report.notes.iter().any(|n| n.starts_with("raw_events")));
+    }
+
+    #[test]
+    fn small_tables_are_left_alone() {
+        let c = catalog();
+        let cfg = AdvisorConfig { min_live_rows: 100_000, ..AdvisorConfig::default() };
+        let report = advise(&c, None, SERVICE_QUERY_PATTERNS, &cfg, Utc::now());
+        assert!(!report.pg_stat_statements_available);
+        assert!(!report.recommendations.iter().any(|r| r.table == "agents"));
+    }
+}
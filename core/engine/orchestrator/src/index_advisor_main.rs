@@ -0,0 +1,122 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/orchestrator/src/index_advisor_main.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Standalone index advisor binary - reads pg_stat_statements, live indexes and scan statistics and prints index recommendations (JSON or text) for an operator to review; never creates indexes.
+
+use std::process;
+
+use chrono::Utc;
+use tracing::error;
+
+#[path = "lib.rs"]
+mod orchestrator;
+
+use orchestrator::db::{CoreDb, DbConfig};
+use orchestrator::index_advisor::{self, AdvisorConfig, SERVICE_QUERY_PATTERNS};
+
+fn usage_and_exit() -> ! {
+    eprintln!("RansomEye Index Advisor");
+    eprintln!("");
+    eprintln!("USAGE:");
+    eprintln!("  ransomeye_index_advisor [--format text|json] [--output <file>] [--min-rows <n>] [--top-statements <n>]");
+    eprintln!("");
+    eprintln!("NOTES:");
+    eprintln!("  - Read-only: recommendations are printed, never applied.");
+    eprintln!("  - Uses pg_stat_statements when the extension is installed, service query patterns otherwise.");
+    eprintln!("  - DB env vars are required: DB_HOST, DB_PORT, DB_NAME, DB_USER, DB_PASS");
+    eprintln!("  - Exit code 0 = no recommendations, 3 = recommendations found");
+    process::exit(2);
+}
+
+fn arg_value(name: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter()
+        .position(|a| a == name)
+        .and_then(|i| args.get(i + 1).cloned())
+}
+
+fn arg_i64(name: &str, default_value: i64) -> i64 {
+    match arg_value(name) {
+        None => default_value,
+        Some(v) => match v.parse::<i64>() {
+            Ok(n) if n >= 0 => n,
+            _ => usage_and_exit(),
+        },
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    orchestrator::BUILD_INFO.handle_version_flag("ransomeye_index_advisor");
+    tracing_subscriber::fmt().with_writer(std::io::stderr).init();
+
+    if std::env::args().any(|a| a == "--help" || a == "-h") {
+        usage_and_exit();
+    }
+
+    let json = match arg_value("--format").as_deref() {
+        None | Some("text") => false,
+        Some("json") => true,
+        Some(_) => usage_and_exit(),
+    };
+    let defaults = AdvisorConfig::default();
+    let advisor_cfg = AdvisorConfig {
+        min_live_rows: arg_i64("--min-rows", defaults.min_live_rows),
+        top_statements: arg_i64("--top-statements", defaults.top_statements),
+    };
+
+    let cfg = match DbConfig::from_env_strict() {
+        Ok(c) => c,
+        Err(e) => {
+            error!("{e}");
+            process::exit(1);
+        }
+    };
+
+    let db = match CoreDb::connect_strict(&cfg).await {
+        Ok(db) => db,
+        Err(e) => {
+            error!("FAIL-CLOSED: DB connect failed: {e}");
+            process::exit(1);
+        }
+    };
+
+    let catalog = match index_advisor::load_live_catalog(&db).await {
+        Ok(c) => c,
+        Err(e) => {
+            error!("{e}");
+            process::exit(1);
+        }
+    };
+    let statements = match index_advisor::load_statements(&db, advisor_cfg.top_statements).await {
+        Ok(s) => s,
+        Err(e) => {
+            error!("{e}");
+            process::exit(1);
+        }
+    };
+
+    let report = index_advisor::advise(&catalog, statements.as_deref(), SERVICE_QUERY_PATTERNS, &advisor_cfg, Utc::now());
+    let rendered = if json {
+        match serde_json::to_string_pretty(&report) {
+            Ok(s) => s + "\n",
+            Err(e) => {
+                error!("Failed to serialize index advisor report: {e}");
+                process::exit(1);
+            }
+        }
+    } else {
+        report.render_text()
+    };
+
+    match arg_value("--output") {
+        Some(path) => {
+            if let Err(e) = std::fs::write(&path, rendered) {
+                error!("Failed to write index advisor report to {}: {}", path, e);
+                process::exit(1);
+            }
+        }
+        None => print!("{rendered}"),
+    }
+
+    process::exit(if report.recommendations.is_empty() { 0 } else { 3 });
+}
@@ -8,15 +8,53 @@ use tokio::signal;
 use tracing::{info, error, warn};
 use thiserror::Error;
 
+use health::HealthStatus;
 use kernel::Kernel;
 use policy::{PolicyEngine, PolicyError};
 use bus::{BusClient, BusClientError, ComponentRole};
-use sha2::Digest;
 
 pub mod db;
 use db::{CoreDb, DbConfig};
 
+pub mod asset_tags;
 pub mod retention_enforcer;
+pub mod orphan_gc;
+pub mod replay;
+pub mod policy_simulation;
+pub mod schema_diff;
+pub mod index_advisor;
+pub mod coverage;
+pub mod attack_coverage;
+pub mod deception_analytics;
+pub mod usage_stats;
+pub mod webhook_dispatcher;
+pub mod config_rollout;
+pub mod crash_report;
+pub mod update_bundle;
+
+pub mod config_drift;
+use config_drift::{DriftConfig, DriftReport, EnvSnapshot};
+
+pub mod lite;
+use lite::{LiteConfig, LiteRuntime, RunMode};
+
+pub mod service_registry;
+use service_registry::{ServiceRegistry, ServiceRegistryConfig, StatusState};
+
+pub mod service_graph;
+use service_graph::{GraphError, Node, NodeKind, ServiceGraph};
+
+pub mod health_server;
+use health_server::{AuditTrail, HealthState};
+
+pub mod capacity_forecast;
+use capacity_forecast::{CapacityConfig, CapacityForecast, CapacityLevel};
+
+pub mod shutdown_hooks;
+use shutdown_hooks::{HookOutcome, HookResult, ShutdownConfig, ShutdownHook, ShutdownHooks};
+
+/// Build provenance of the engine binaries (commit, Cargo.lock hash, SBOM); see build.rs
+pub static BUILD_INFO: build_info::BuildInfo = build_info::build_info!();
 
 #[derive(Debug, Error)]
 pub enum OrchestratorError {
@@ -44,6 +82,8 @@ pub enum OrchestratorError {
     RetentionDryRunValidationFailed(String),
     #[error("Shutdown failed: {0}")]
     ShutdownFailed(String),
+    #[error("Service dependency graph invalid: {0}")]
+    DependencyGraphInvalid(#[from] GraphError),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -70,15 +110,152 @@ pub enum OrchestratorState {
     Failed,
 }
 
+impl OrchestratorState {
+    /// Name reported by the status API and heartbeat acknowledgements.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrchestratorState::Initializing => "INITIALIZING",
+            OrchestratorState::EnvironmentValidated => "ENVIRONMENT_VALIDATED",
+            OrchestratorState::TrustInitialized => "TRUST_INITIALIZED",
+            OrchestratorState::PolicyInitialized => "POLICY_INITIALIZED",
+            OrchestratorState::BusInitialized => "BUS_INITIALIZED",
+            OrchestratorState::ServicesInitialized => "SERVICES_INITIALIZED",
+            OrchestratorState::Ready => "READY",
+            OrchestratorState::Running => "RUNNING",
+            OrchestratorState::ShuttingDown => "SHUTTING_DOWN",
+            OrchestratorState::Failed => "FAILED",
+        }
+    }
+}
+
+/// Orchestrator startup steps, declared in the dependency graph in this order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Subsystem {
+    Environment,
+    StatusListener,
+    HealthListener,
+    Storage,
+    Trust,
+    Policy,
+    IngestQuotas,
+    Bus,
+    Services,
+    EmbeddedIngest,
+    WebhookDispatcher,
+    ConfigRollout,
+    HealthGate,
+}
+
+impl Subsystem {
+    pub const ALL: [Subsystem; 13] = [
+        Subsystem::Environment,
+        Subsystem::StatusListener,
+        Subsystem::HealthListener,
+        Subsystem::Storage,
+        Subsystem::Trust,
+        Subsystem::Policy,
+        Subsystem::IngestQuotas,
+        Subsystem::Bus,
+        Subsystem::Services,
+        Subsystem::EmbeddedIngest,
+        Subsystem::WebhookDispatcher,
+        Subsystem::ConfigRollout,
+        Subsystem::HealthGate,
+    ];
+
+    /// Node name in the dependency graph (and in RANSOMEYE_SERVICE_DEPENDENCIES).
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Subsystem::Environment => "environment",
+            Subsystem::StatusListener => "status_listener",
+            Subsystem::HealthListener => "health_listener",
+            Subsystem::Storage => "storage",
+            Subsystem::Trust => "trust",
+            Subsystem::Policy => "policy",
+            Subsystem::IngestQuotas => "ingest_quotas",
+            Subsystem::Bus => "bus",
+            Subsystem::Services => "services",
+            Subsystem::EmbeddedIngest => "embedded_ingest",
+            Subsystem::WebhookDispatcher => "webhook_dispatcher",
+            Subsystem::ConfigRollout => "config_rollout",
+            Subsystem::HealthGate => "health_gate",
+        }
+    }
+
+    /// Subsystems this one needs started first. The health gate also depends on every service.
+    pub fn depends_on(&self) -> &'static [&'static str] {
+        match self {
+            Subsystem::Environment => &[],
+            Subsystem::StatusListener => &["environment"],
+            Subsystem::HealthListener => &["environment"],
+            Subsystem::Storage => &["environment"],
+            Subsystem::Trust => &["storage"],
+            Subsystem::Policy => &["trust"],
+            Subsystem::IngestQuotas => &["policy", "storage"],
+            Subsystem::Bus => &["trust"],
+            Subsystem::Services => &["bus", "policy"],
+            Subsystem::EmbeddedIngest => &["services"],
+            Subsystem::WebhookDispatcher => &["storage", "services"],
+            Subsystem::ConfigRollout => &["storage", "services"],
+            Subsystem::HealthGate => &["trust", "policy", "services", "status_listener"],
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.as_str() == name)
+    }
+}
+
+/// Dependencies every managed service has, in addition to RANSOMEYE_SERVICE_DEPENDENCIES.
+const SERVICE_BASE_DEPENDENCIES: &[&str] = &["services", "status_listener"];
+
+/// Dependency graph of the subsystems and the required services (see service_graph.rs).
+///
+/// FAIL-CLOSED: Returns error for dependencies of a service that is not required, a missing
+/// dependency or a cycle
+pub fn dependency_graph(registry_cfg: &ServiceRegistryConfig) -> Result<ServiceGraph, OrchestratorError> {
+    let mut graph = ServiceGraph::new();
+    for subsystem in Subsystem::ALL.iter().filter(|s| **s != Subsystem::HealthGate) {
+        graph.subsystem(subsystem.as_str(), subsystem.depends_on());
+    }
+    for service in &registry_cfg.required {
+        graph.service(service, SERVICE_BASE_DEPENDENCIES);
+    }
+    for (service, deps) in &registry_cfg.dependencies {
+        if graph.get(service).map(|n| n.kind) != Some(NodeKind::Service) {
+            return Err(OrchestratorError::EnvironmentValidationFailed(format!(
+                "RANSOMEYE_SERVICE_DEPENDENCIES names '{}', which is not in RANSOMEYE_REQUIRED_SERVICES",
+                service
+            )));
+        }
+        graph
+            .add_dependencies(service, deps)
+            .map_err(OrchestratorError::EnvironmentValidationFailed)?;
+    }
+    // The health gate comes last: it needs every required service serving
+    graph.subsystem(Subsystem::HealthGate.as_str(), Subsystem::HealthGate.depends_on());
+    graph
+        .add_dependencies(Subsystem::HealthGate.as_str(), &registry_cfg.required)
+        .map_err(OrchestratorError::EnvironmentValidationFailed)?;
+    graph.startup_order()?;
+    Ok(graph)
+}
+
 /// Core Orchestrator with fail-closed guarantees
-/// 
-/// Enforces strict startup order:
-/// 1. Environment validation
-/// 2. Trust subsystem
-/// 3. Policy engine
-/// 4. Event bus
-/// 5. Core services
-/// 6. Health gate
+///
+/// Startup order is derived from the dependency graph of its subsystems and the required
+/// services (see `dependency_graph`); by default:
+/// 1. Environment validation (then the status and health listeners)
+/// 2. Storage
+/// 3. Trust subsystem
+/// 4. Policy engine
+/// 5. Event bus
+/// 6. Core services
+/// 7. Required services reporting through heartbeats, in dependency order
+/// 8. Health gate
+///
+/// Shutdown runs in exactly the reverse order, awaiting the shutdown hooks registered for each
+/// subsystem before it stops.
 pub struct Orchestrator {
     state: Arc<AtomicBool>,
     kernel: Option<Arc<Kernel>>,
@@ -90,6 +267,46 @@ pub struct Orchestrator {
     startup_health_id: Option<uuid::Uuid>,
     current_state: Arc<parking_lot::RwLock<OrchestratorState>>,
     dry_run: bool,
+    drift_cfg: DriftConfig,
+    startup_env: Option<EnvSnapshot>,
+    last_drift_fingerprint: parking_lot::Mutex<Option<String>>,
+    config_drift_unhealthy: Arc<AtomicBool>,
+    log_override: Option<LogOverrideControl>,
+    mode: RunMode,
+    lite_cfg: Option<LiteConfig>,
+    lite: Option<LiteRuntime>,
+    webhook_task: Option<tokio::task::JoinHandle<()>>,
+    rollout_task: Option<tokio::task::JoinHandle<()>>,
+    registry_cfg: ServiceRegistryConfig,
+    registry: Arc<ServiceRegistry>,
+    status_task: Option<tokio::task::JoinHandle<()>>,
+    /// Probe listener (RANSOMEYE_ORCH_HEALTH_ADDR); None = disabled
+    health_addr: Option<std::net::SocketAddr>,
+    health_task: Option<tokio::task::JoinHandle<()>>,
+    /// Ids of the last rows written about this orchestrator, served by GET /state
+    audit_trail: Arc<parking_lot::RwLock<AuditTrail>>,
+    capacity_cfg: CapacityConfig,
+    /// Latest capacity forecast, reported by the status API
+    capacity: Arc<parking_lot::RwLock<Option<CapacityForecast>>>,
+    shutdown_cfg: ShutdownConfig,
+    /// Flush/teardown callbacks awaited on shutdown, by subsystem
+    shutdown_hooks: ShutdownHooks,
+    graph: ServiceGraph,
+    /// components rows of heartbeating service instances, keyed by (service, instance_id)
+    service_components: parking_lot::Mutex<std::collections::HashMap<(String, String), uuid::Uuid>>,
+    /// Last reported health status recorded in component_health, keyed by (service, instance_id)
+    service_health: parking_lot::Mutex<std::collections::HashMap<(String, String), HealthStatus>>,
+    services_degraded: AtomicBool,
+    /// Periodic checks whose last run failed (check -> error); the orchestrator is degraded while any is
+    failed_checks: Arc<parking_lot::RwLock<std::collections::BTreeMap<&'static str, String>>>,
+}
+
+/// SIGUSR1 log-level override: applies `signal_filter` for `ttl`, then restores `baseline`.
+struct LogOverrideControl {
+    handle: kernel::otel::LogFilterHandle,
+    baseline: String,
+    signal_filter: String,
+    ttl: std::time::Duration,
 }
 
 impl Orchestrator {
@@ -100,6 +317,31 @@ impl Orchestrator {
             .unwrap_or_else(|_| "0".to_string())
             == "1";
 
+        let drift_cfg = DriftConfig::from_env()
+            .map_err(OrchestratorError::EnvironmentValidationFailed)?;
+
+        // Standalone single-node mode is opt-in only (RANSOMEYE_MODE=lite)
+        let mode = RunMode::from_env()
+            .map_err(OrchestratorError::EnvironmentValidationFailed)?;
+        let lite_cfg = match mode {
+            RunMode::Lite => Some(LiteConfig::from_env().map_err(OrchestratorError::EnvironmentValidationFailed)?),
+            RunMode::Full => None,
+        };
+
+        // Heartbeat/status listener and required services (FAIL-CLOSED on an unauthenticated remote bind)
+        let registry_cfg = ServiceRegistryConfig::from_env()
+            .map_err(OrchestratorError::EnvironmentValidationFailed)?;
+        let registry = Arc::new(ServiceRegistry::new(registry_cfg.required.clone(), registry_cfg.stale_after));
+        let health_addr = health_server::listen_addr_from_env(registry_cfg.token.is_some())
+            .map_err(OrchestratorError::EnvironmentValidationFailed)?;
+        let capacity_cfg = CapacityConfig::from_env()
+            .map_err(OrchestratorError::EnvironmentValidationFailed)?;
+        let shutdown_cfg = ShutdownConfig::from_env()
+            .map_err(OrchestratorError::EnvironmentValidationFailed)?;
+
+        // FAIL-CLOSED: startup order must be derivable before anything starts
+        let graph = dependency_graph(&registry_cfg)?;
+
         Ok(Self {
             state: Arc::new(AtomicBool::new(false)),
             kernel: None,
@@ -111,9 +353,109 @@ impl Orchestrator {
             startup_health_id: None,
             current_state: Arc::new(parking_lot::RwLock::new(OrchestratorState::Initializing)),
             dry_run,
+            drift_cfg,
+            startup_env: None,
+            last_drift_fingerprint: parking_lot::Mutex::new(None),
+            config_drift_unhealthy: Arc::new(AtomicBool::new(false)),
+            log_override: None,
+            mode,
+            lite_cfg,
+            lite: None,
+            webhook_task: None,
+            rollout_task: None,
+            registry_cfg,
+            registry,
+            status_task: None,
+            health_addr,
+            health_task: None,
+            audit_trail: Arc::new(parking_lot::RwLock::new(AuditTrail::default())),
+            capacity_cfg,
+            capacity: Arc::new(parking_lot::RwLock::new(None)),
+            shutdown_cfg,
+            shutdown_hooks: ShutdownHooks::default(),
+            graph,
+            service_components: parking_lot::Mutex::new(std::collections::HashMap::new()),
+            service_health: parking_lot::Mutex::new(std::collections::HashMap::new()),
+            services_degraded: AtomicBool::new(false),
+            failed_checks: Arc::new(parking_lot::RwLock::new(std::collections::BTreeMap::new())),
         })
     }
 
+    /// Run mode selected at construction (RANSOMEYE_MODE).
+    pub fn mode(&self) -> RunMode {
+        self.mode
+    }
+
+    /// Register a flush/teardown callback, awaited when `subsystem` stops (see shutdown_hooks.rs).
+    pub fn register_shutdown_hook(&mut self, subsystem: Subsystem, hook: Arc<dyn ShutdownHook>) {
+        info!("Shutdown hook '{}' registered for {}", hook.name(), subsystem.as_str());
+        self.shutdown_hooks.register(subsystem.as_str(), hook);
+    }
+
+    fn skip_policy(&self) -> bool {
+        self.lite_cfg.as_ref().map(|c| c.skip_policy).unwrap_or(false)
+    }
+
+    /// Enable SIGUSR1 log-level overrides (RANSOMEYE_SIGUSR1_LOG_FILTER for RANSOMEYE_RUNTIME_OVERRIDE_TTL_SECS).
+    pub fn set_log_filter(&mut self, handle: kernel::otel::LogFilterHandle, baseline: &str) -> Result<(), OrchestratorError> {
+        let signal_filter = std::env::var("RANSOMEYE_SIGUSR1_LOG_FILTER").unwrap_or_else(|_| "debug".to_string());
+        signal_filter
+            .parse::<tracing_subscriber::filter::Targets>()
+            .map_err(|e| OrchestratorError::EnvironmentValidationFailed(format!(
+                "Invalid RANSOMEYE_SIGUSR1_LOG_FILTER '{}': {}", signal_filter, e
+            )))?;
+        let ttl_secs = match std::env::var("RANSOMEYE_RUNTIME_OVERRIDE_TTL_SECS") {
+            Ok(v) => v.parse::<u64>().ok().filter(|t| *t > 0).ok_or_else(|| {
+                OrchestratorError::EnvironmentValidationFailed(format!(
+                    "Invalid RANSOMEYE_RUNTIME_OVERRIDE_TTL_SECS='{}' (expected integer > 0)", v
+                ))
+            })?,
+            Err(_) => 900,
+        };
+        self.log_override = Some(LogOverrideControl {
+            handle,
+            baseline: baseline.to_string(),
+            signal_filter,
+            ttl: std::time::Duration::from_secs(ttl_secs),
+        });
+        Ok(())
+    }
+
+    /// Swap the active log filter and audit the change. Returns false if overrides are not enabled.
+    async fn set_runtime_log_filter(&self, override_active: bool, trigger: &str) -> Result<bool, OrchestratorError> {
+        let Some(ctl) = &self.log_override else {
+            warn!("Runtime log override requested (trigger={}) but no reloadable log filter is installed", trigger);
+            return Ok(false);
+        };
+        let filter = if override_active { &ctl.signal_filter } else { &ctl.baseline };
+        let targets = filter
+            .parse::<tracing_subscriber::filter::Targets>()
+            .map_err(|e| OrchestratorError::ComponentInitFailed(format!("Invalid log filter '{}': {}", filter, e)))?;
+        ctl.handle
+            .reload(targets)
+            .map_err(|e| OrchestratorError::ComponentInitFailed(format!("Log filter reload failed: {}", e)))?;
+        info!("Runtime log filter set to '{}' (trigger={})", filter, trigger);
+
+        let action = if override_active { "orchestrator_runtime_config_override" } else { "orchestrator_runtime_config_revert" };
+        let payload = serde_json::json!({
+            "trigger": trigger,
+            "log_filter": filter,
+            "baseline_log_filter": ctl.baseline,
+            "ttl_secs": if override_active { Some(ctl.ttl.as_secs()) } else { None },
+        });
+        if let (Some(db), Some(component_id)) = (self.db.as_ref(), self.component_db_id) {
+            let audit_id = db
+                .insert_immutable_audit_log(Some(component_id), action, "other", Some(component_id), &payload)
+                .await
+                .map_err(OrchestratorError::DatabaseWriteFailed)?;
+            self.note_audit(action, audit_id);
+        } else if let Some(lite) = &self.lite {
+            let audit_id = lite.audit(action, &payload).await.map_err(OrchestratorError::DatabaseWriteFailed)?;
+            self.note_audit(action, audit_id);
+        }
+        Ok(true)
+    }
+
     /// Set orchestrator state (internal)
     fn set_state(&self, new_state: OrchestratorState) {
         let mut state = self.current_state.write();
@@ -126,18 +468,33 @@ impl Orchestrator {
         *self.current_state.read()
     }
 
+    /// Ids of the last startup, component_health and audit rows written about this orchestrator.
+    pub fn audit_trail(&self) -> AuditTrail {
+        self.audit_trail.read().clone()
+    }
+
+    fn note_audit(&self, action: &str, audit_id: uuid::Uuid) {
+        self.audit_trail.write().record_audit(action, audit_id, chrono::Utc::now());
+    }
+
+    fn note_health(&self, health_id: uuid::Uuid) {
+        self.audit_trail.write().record_health(health_id, chrono::Utc::now());
+    }
+
     /// Validate required environment variables
     /// 
     /// FAIL-CLOSED: Returns error if any required env var is missing
     fn validate_environment(&self) -> Result<(), OrchestratorError> {
         info!("Validating environment...");
 
-        // Required environment variables
-        let required_vars = vec![
+        // Required environment variables (lite mode may explicitly drop the policy store)
+        let mut required_vars = vec![
             "RANSOMEYE_ROOT_KEY_PATH",
-            "RANSOMEYE_POLICY_DIR",
             "RANSOMEYE_TRUST_STORE_PATH",
         ];
+        if !self.skip_policy() {
+            required_vars.push("RANSOMEYE_POLICY_DIR");
+        }
 
         let mut missing = Vec::new();
         for var in required_vars {
@@ -160,11 +517,13 @@ impl Orchestrator {
             ));
         }
 
-        let policy_dir = std::env::var("RANSOMEYE_POLICY_DIR").unwrap();
-        if !std::path::Path::new(&policy_dir).exists() {
-            return Err(OrchestratorError::EnvironmentValidationFailed(
-                format!("Policy directory not found: {}", policy_dir)
-            ));
+        if !self.skip_policy() {
+            let policy_dir = std::env::var("RANSOMEYE_POLICY_DIR").unwrap();
+            if !std::path::Path::new(&policy_dir).exists() {
+                return Err(OrchestratorError::EnvironmentValidationFailed(
+                    format!("Policy directory not found: {}", policy_dir)
+                ));
+            }
         }
 
         let trust_store = std::env::var("RANSOMEYE_TRUST_STORE_PATH").unwrap();
@@ -205,10 +564,25 @@ impl Orchestrator {
             .await
             .map_err(OrchestratorError::DatabaseSchemaValidationFailed)?;
 
+        // Rolling-upgrade compatibility: record the baseline generation on older databases, install
+        // the dual-write shims migrations use for renames, then refuse a schema more than one
+        // generation away from this build.
+        ingest::schema_compat::ensure_baseline(db.client())
+            .await
+            .map_err(OrchestratorError::DatabaseSchemaApplyFailed)?;
+        ingest::schema_compat::install_shims(db.client())
+            .await
+            .map_err(OrchestratorError::DatabaseSchemaApplyFailed)?;
+        ingest::schema_compat::enforce(db.client(), "ransomeye_orchestrator", &[])
+            .await
+            .map_err(OrchestratorError::DatabaseSchemaValidationFailed)?;
+
         // Upsert orchestrator component (FK anchor for core runtime tables).
-        let build_hash = std::env::var("RANSOMEYE_BUILD_HASH").ok();
-        let version = std::env::var("RANSOMEYE_VERSION").ok();
+        // Env overrides win; otherwise the commit and version embedded at build time.
+        let build_hash = Some(std::env::var("RANSOMEYE_BUILD_HASH").unwrap_or_else(|_| BUILD_INFO.git_commit.to_string()));
+        let version = Some(std::env::var("RANSOMEYE_VERSION").unwrap_or_else(|_| BUILD_INFO.version.to_string()));
         let instance_id = std::env::var("RANSOMEYE_INSTANCE_ID").ok();
+        let build_metadata = BUILD_INFO.metadata();
 
         let component_db_id = db
             .upsert_component(
@@ -217,40 +591,15 @@ impl Orchestrator {
                 instance_id.as_deref(),
                 build_hash.as_deref(),
                 version.as_deref(),
+                Some(&build_metadata),
             )
             .await
             .map_err(OrchestratorError::DatabaseWriteFailed)?;
 
         // Compute a non-secret environment fingerprint (hash only; excludes DB_PASS and other secrets).
-        let env_fingerprint = {
-            let mut pairs: Vec<(String, String)> = Vec::new();
-            for k in [
-                "DB_HOST",
-                "DB_PORT",
-                "DB_NAME",
-                "DB_USER",
-                "RANSOMEYE_ROOT_KEY_PATH",
-                "RANSOMEYE_POLICY_DIR",
-                "RANSOMEYE_TRUST_STORE_PATH",
-                "RANSOMEYE_SCHEMA_SQL_PATH",
-            ] {
-                if let Ok(v) = std::env::var(k) {
-                    pairs.push((k.to_string(), v));
-                }
-            }
-            pairs.sort_by(|a, b| a.0.cmp(&b.0));
-            let mut canonical = String::new();
-            for (k, v) in pairs {
-                canonical.push_str(&k);
-                canonical.push('=');
-                canonical.push_str(&v);
-                canonical.push('\n');
-            }
-            let mut hasher = sha2::Sha256::new();
-            hasher.update(canonical.as_bytes());
-            let digest = hasher.finalize();
-            digest.to_vec()
-        };
+        // The snapshot is retained as the drift baseline for periodic/reload comparisons.
+        let startup_env = EnvSnapshot::capture(self.drift_cfg.env_file.as_deref());
+        let env_fingerprint = startup_env.fingerprint();
 
         let startup_event_id = db
             .insert_startup_event(
@@ -262,7 +611,8 @@ impl Orchestrator {
                 Some(&env_fingerprint),
                 Some(&serde_json::json!({
                     "component": "ransomeye_orchestrator",
-                    "component_type": "master_core"
+                    "component_type": "master_core",
+                    "build": build_metadata
                 })),
             )
             .await
@@ -321,15 +671,379 @@ impl Orchestrator {
             retention_audit_id
         );
 
-        self.db = Some(Arc::new(db));
+        let db = Arc::new(db);
+        let writer = db.clone();
+        self.register_shutdown_hook(
+            Subsystem::Storage,
+            Arc::new(shutdown_hooks::hook("db_writer", move || {
+                let db = writer.clone();
+                Box::pin(async move { db.flush().await })
+            })),
+        );
+        self.db = Some(db);
         self.component_db_id = Some(component_db_id);
+        crash_report::set_component_id(component_db_id);
         self.startup_event_id = Some(startup_event_id);
         self.startup_health_id = Some(health_id);
+        self.startup_env = Some(startup_env);
+        {
+            let mut trail = self.audit_trail.write();
+            trail.startup_event_id = Some(startup_event_id);
+            trail.startup_health_id = Some(health_id);
+        }
+        self.note_health(health_id);
+        self.note_audit("orchestrator_db_initialized", audit_id);
+        self.note_audit("runtime_retention_dry_run", retention_audit_id);
+        Ok(())
+    }
+
+    /// Lite mode replacement for `initialize_database`: open the SQLite store shared with the
+    /// embedded ingest server and audit the start. No schema contract or retention dry-run.
+    async fn initialize_lite_storage(&mut self) -> Result<(), OrchestratorError> {
+        let cfg = self.lite_cfg.clone().ok_or_else(|| {
+            OrchestratorError::EnvironmentValidationFailed("Lite storage requested outside lite mode".to_string())
+        })?;
+        lite::warn_lite_limitations(&cfg);
+
+        let runtime = LiteRuntime::open(&cfg)
+            .await
+            .map_err(OrchestratorError::DatabaseConnectionFailed)?;
+        let startup_env = EnvSnapshot::capture(self.drift_cfg.env_file.as_deref());
+        let audit_id = runtime
+            .audit(
+                "orchestrator_lite_initialized",
+                &serde_json::json!({
+                    "status": "STARTING",
+                    "mode": "lite",
+                    "sqlite_path": cfg.sqlite_path.display().to_string(),
+                    "skip_policy": cfg.skip_policy,
+                    "env_fingerprint": startup_env.fingerprint()
+                }),
+            )
+            .await
+            .map_err(OrchestratorError::DatabaseWriteFailed)?;
+        info!("Lite storage initialized (immutable_audit_log.audit_id={})", audit_id);
+        self.note_audit("orchestrator_lite_initialized", audit_id);
+
+        self.lite = Some(runtime);
+        self.startup_env = Some(startup_env);
+        Ok(())
+    }
+
+    /// Lite mode: start the ingest HTTP server inside this process.
+    fn start_embedded_ingest(&mut self) -> Result<(), OrchestratorError> {
+        let (Some(cfg), Some(runtime)) = (self.lite_cfg.as_ref(), self.lite.as_mut()) else {
+            return Err(OrchestratorError::ComponentInitFailed("Lite runtime not initialized".to_string()));
+        };
+        let log_filter = self.log_override.as_ref().map(|ctl| (ctl.handle.clone(), ctl.baseline.clone()));
+        runtime
+            .start_ingest(cfg, log_filter)
+            .map_err(|e| OrchestratorError::ComponentInitFailed(format!("Embedded ingest: {}", e)))
+    }
+
+    /// Recompute the environment fingerprint and compare it with the startup value.
+    ///
+    /// Drift is logged, audited once per distinct fingerprint, and (if configured) marks the
+    /// component unhealthy when a critical variable changed. Returns the drift report, if any.
+    pub async fn check_config_drift(&self, trigger: &str) -> Result<Option<DriftReport>, OrchestratorError> {
+        let Some(startup_env) = &self.startup_env else {
+            return Ok(None);
+        };
+
+        let current = EnvSnapshot::capture(self.drift_cfg.env_file.as_deref());
+        let Some(report) = config_drift::detect_drift(startup_env, &current, &self.drift_cfg.critical_vars) else {
+            // Drift reverted: clear state so a future drift is reported again.
+            if self.last_drift_fingerprint.lock().take().is_some() {
+                info!("Config drift cleared (trigger={}); environment matches startup fingerprint", trigger);
+                self.config_drift_unhealthy.store(false, Ordering::SeqCst);
+            }
+            return Ok(None);
+        };
+
+        {
+            let mut last = self.last_drift_fingerprint.lock();
+            if last.as_deref() == Some(report.current_fingerprint_hex.as_str()) {
+                return Ok(Some(report));
+            }
+            *last = Some(report.current_fingerprint_hex.clone());
+        }
+
+        let mark_unhealthy = report.is_critical() && self.drift_cfg.unhealthy_on_critical;
+        warn!(
+            "Config drift detected (trigger={}): changed={:?} critical={:?} startup_fp={} current_fp={}",
+            trigger,
+            report.changed_vars,
+            report.critical_changed,
+            report.startup_fingerprint_hex,
+            report.current_fingerprint_hex
+        );
+        if mark_unhealthy {
+            self.config_drift_unhealthy.store(true, Ordering::SeqCst);
+            error!("Critical config drift - marking orchestrator UNHEALTHY until restart or revert");
+        }
+
+        if let (Some(db), Some(component_id)) = (self.db.as_ref(), self.component_db_id) {
+            let payload = serde_json::json!({
+                "trigger": trigger,
+                "startup_event_id": self.startup_event_id.map(|x| x.to_string()),
+                "changed_vars": report.changed_vars,
+                "critical_changed": report.critical_changed,
+                "startup_env_fingerprint_sha256": report.startup_fingerprint_hex,
+                "current_env_fingerprint_sha256": report.current_fingerprint_hex,
+                "marked_unhealthy": mark_unhealthy
+            });
+
+            let audit_id = db
+                .insert_immutable_audit_log(
+                    Some(component_id),
+                    "orchestrator_config_drift",
+                    "other",
+                    Some(component_id),
+                    &payload,
+                )
+                .await
+                .map_err(OrchestratorError::DatabaseWriteFailed)?;
+            self.note_audit("orchestrator_config_drift", audit_id);
+
+            let health_id = db
+                .insert_component_health(
+                    component_id,
+                    if mark_unhealthy { "unhealthy" } else { "degraded" },
+                    Some("config_drift"),
+                    Some(&payload),
+                )
+                .await
+                .map_err(OrchestratorError::DatabaseWriteFailed)?;
+            self.note_health(health_id);
+        }
+
+        Ok(Some(report))
+    }
+
+    /// Drain service liveness transitions and record them.
+    ///
+    /// Instances that sent a heartbeat since the last check get components.last_heartbeat_at bumped,
+    /// and a component_health row when the status of their health report changed; every transition
+    /// is audited and recorded as component_health of that instance. The orchestrator itself is
+    /// degraded while a required service has no serving instance.
+    pub async fn check_service_liveness(&self) -> Result<(), OrchestratorError> {
+        let now = std::time::Instant::now();
+        let outcome = self.registry.sweep(now);
+
+        if let Some(db) = self.db.as_ref() {
+            for hb in &outcome.heartbeats {
+                let build_hash = hb.build.as_ref().and_then(|b| b.get("git_commit")).and_then(|c| c.as_str());
+                let component_id = db
+                    .upsert_component(
+                        "core_engine",
+                        &hb.service,
+                        Some(&hb.instance_id),
+                        build_hash,
+                        hb.version.as_deref(),
+                        hb.build.as_ref(),
+                    )
+                    .await
+                    .map_err(OrchestratorError::DatabaseWriteFailed)?;
+                let key = (hb.service.clone(), hb.instance_id.clone());
+                self.service_components.lock().insert(key.clone(), component_id);
+
+                if let Some(report) = &hb.health {
+                    let previous = self.service_health.lock().insert(key, report.status);
+                    if previous != Some(report.status) {
+                        db.insert_component_health(
+                            component_id,
+                            report.status.as_str(),
+                            report.status_details().as_deref(),
+                            Some(&report.metrics_json()),
+                        )
+                        .await
+                        .map_err(OrchestratorError::DatabaseWriteFailed)?;
+                    }
+                }
+            }
+        }
+
+        let missing = self.registry.missing_required(now);
+        for change in &outcome.changes {
+            if change.is_stale() {
+                warn!("Service heartbeat lost: {:?}", change);
+            } else {
+                info!("Service liveness: {:?}", change);
+            }
+            let payload = serde_json::json!({
+                "change": change,
+                "required": self.registry.is_required(change.service()),
+                "missing_required": missing,
+                "orchestrator_instance": self.registry.instance().to_string()
+            });
+
+            if let (Some(db), Some(component_id)) = (self.db.as_ref(), self.component_db_id) {
+                let audit_id = db
+                    .insert_immutable_audit_log(
+                        Some(component_id),
+                        "orchestrator_service_liveness",
+                        "other",
+                        Some(component_id),
+                        &payload,
+                    )
+                    .await
+                    .map_err(OrchestratorError::DatabaseWriteFailed)?;
+                self.note_audit("orchestrator_service_liveness", audit_id);
+
+                let service_component = self
+                    .service_components
+                    .lock()
+                    .get(&(change.service().to_string(), change.instance_id().to_string()))
+                    .copied();
+                if let Some(service_component) = service_component {
+                    db.insert_component_health(
+                        service_component,
+                        if change.is_stale() { HealthStatus::Unhealthy.as_str() } else { HealthStatus::Healthy.as_str() },
+                        Some("heartbeat"),
+                        Some(&payload),
+                    )
+                    .await
+                    .map_err(OrchestratorError::DatabaseWriteFailed)?;
+                }
+            }
+            if let Some(lite) = &self.lite {
+                let audit_id = lite
+                    .audit("orchestrator_service_liveness", &payload)
+                    .await
+                    .map_err(OrchestratorError::DatabaseWriteFailed)?;
+                self.note_audit("orchestrator_service_liveness", audit_id);
+            }
+        }
+
+        let degraded = !missing.is_empty();
+        if self.services_degraded.swap(degraded, Ordering::SeqCst) != degraded {
+            if degraded {
+                error!("Required services not serving: {} - orchestrator DEGRADED", missing.join(", "));
+            } else {
+                info!("All required services serving again");
+            }
+            if let (Some(db), Some(component_id)) = (self.db.as_ref(), self.component_db_id) {
+                let health_id = db
+                    .insert_component_health(
+                        component_id,
+                        if degraded { HealthStatus::Degraded.as_str() } else { HealthStatus::Healthy.as_str() },
+                        Some("service_liveness"),
+                        Some(&serde_json::json!({"missing_required": missing})),
+                    )
+                    .await
+                    .map_err(OrchestratorError::DatabaseWriteFailed)?;
+                self.note_health(health_id);
+            }
+        }
         Ok(())
     }
 
+    /// Sample the database size and refresh the capacity forecast (Postgres control plane only).
+    ///
+    /// The forecast is served by the status API. Entering or leaving the low level (days until
+    /// full under RANSOMEYE_DB_CAPACITY_ALERT_DAYS) is audited, recorded as component_health and
+    /// sent as a db.capacity webhook, once per change.
+    pub async fn check_capacity(&self) -> Result<(), OrchestratorError> {
+        let (Some(db), Some(component_id)) = (self.db.as_ref(), self.component_db_id) else {
+            return Ok(());
+        };
+        let now = chrono::Utc::now();
+        capacity_forecast::sample(db)
+            .await
+            .map_err(OrchestratorError::DatabaseWriteFailed)?;
+        let history = capacity_forecast::history(db, self.capacity_cfg.lookback_days, now)
+            .await
+            .map_err(OrchestratorError::DatabaseWriteFailed)?;
+        let retention = capacity_forecast::retention_days(db)
+            .await
+            .map_err(OrchestratorError::DatabaseWriteFailed)?;
+        let forecast = capacity_forecast::forecast(&history, &retention, &self.capacity_cfg, now);
+
+        let previous = self.capacity.write().replace(forecast.clone()).map(|f| f.level);
+        let low = forecast.level == CapacityLevel::Low;
+        if (previous == Some(CapacityLevel::Low)) == low {
+            return Ok(());
+        }
+
+        let data = serde_json::json!({
+            "level": forecast.level.as_str(),
+            "previous_level": previous.map(|l| l.as_str()),
+            "days_until_full": forecast.days_until_full,
+            "projected_full_at": forecast.projected_full_at,
+            "database_bytes": forecast.database_bytes,
+            "capacity_bytes": forecast.capacity_bytes,
+            "growth_bytes_per_day": forecast.growth_bytes_per_day,
+            "alert_days": forecast.alert_days,
+            "fastest_growing": forecast.tables.iter().take(3).map(|t| t.table.as_str()).collect::<Vec<_>>()
+        });
+        let action = if low {
+            error!(
+                "Database capacity LOW: full in {:.1} days (alert below {} days) at {:.0} bytes/day",
+                forecast.days_until_full.unwrap_or(0.0),
+                forecast.alert_days,
+                forecast.growth_bytes_per_day
+            );
+            "db_capacity_alert"
+        } else {
+            info!("Database capacity no longer low (level={})", forecast.level.as_str());
+            "db_capacity_cleared"
+        };
+        ingest::webhooks::enqueue(
+            db.client(),
+            &ingest::webhooks::WebhookEvent::new(ingest::webhooks::DB_CAPACITY, data.clone()),
+        )
+        .await
+        .map_err(|e| OrchestratorError::DatabaseWriteFailed(format!("Failed to enqueue db.capacity webhook: {e}")))?;
+        let audit_id = db
+            .insert_immutable_audit_log(Some(component_id), action, "other", Some(component_id), &data)
+            .await
+            .map_err(OrchestratorError::DatabaseWriteFailed)?;
+        self.note_audit(action, audit_id);
+        let health_id = db
+            .insert_component_health(
+                component_id,
+                if low { HealthStatus::Degraded.as_str() } else { HealthStatus::Healthy.as_str() },
+                Some("db_capacity"),
+                Some(&data),
+            )
+            .await
+            .map_err(OrchestratorError::DatabaseWriteFailed)?;
+        self.note_health(health_id);
+        Ok(())
+    }
+
+    /// Record the outcome of a periodic check run from the main loop. A failure is logged and
+    /// reported as a degraded `<check>_check` subsystem until the check next succeeds; it never
+    /// stops the loop, so shutdown still runs the hooks.
+    fn note_check<T>(&self, check: &'static str, result: Result<T, OrchestratorError>) -> Option<T> {
+        match result {
+            Ok(value) => {
+                if self.failed_checks.write().remove(check).is_some() {
+                    info!("Periodic check '{}' succeeded again", check);
+                }
+                Some(value)
+            }
+            Err(e) => {
+                error!("Periodic check '{}' failed: {} - orchestrator DEGRADED", check, e);
+                self.failed_checks.write().insert(check, e.to_string());
+                None
+            }
+        }
+    }
+
+    /// True if a critical config drift marked this orchestrator unhealthy.
+    pub fn is_config_drift_unhealthy(&self) -> bool {
+        self.config_drift_unhealthy.load(Ordering::SeqCst)
+    }
+
     /// Best-effort: record an error event + audit entry if DB is initialized; never masks the original failure.
     pub async fn record_fatal_error(&self, error_text: &str) {
+        if let Some(lite) = &self.lite {
+            if let Err(e) = lite.audit("orchestrator_fatal_error", &serde_json::json!({"error": error_text})).await {
+                error!("Failed to write immutable_audit_log for fatal error: {}", e);
+            }
+            return;
+        }
         let Some(db) = &self.db else {
             return;
         };
@@ -394,6 +1108,12 @@ impl Orchestrator {
     fn initialize_policy(&mut self) -> Result<(), OrchestratorError> {
         info!("Initializing policy engine...");
 
+        if self.skip_policy() {
+            warn!("Policy store skipped (lite mode, RANSOMEYE_LITE_SKIP_POLICY=1)");
+            self.set_state(OrchestratorState::PolicyInitialized);
+            return Ok(());
+        }
+
         let policy_dir = std::env::var("RANSOMEYE_POLICY_DIR")
             .map_err(|_| OrchestratorError::ComponentInitFailed(
                 "RANSOMEYE_POLICY_DIR not set".to_string()
@@ -422,11 +1142,48 @@ impl Orchestrator {
         )?;
 
         self.policy_engine = Some(Arc::new(policy_engine));
+        // Decisions are appended to the audit log as they are made; make them durable on the way out
+        if let Some(path) = audit_log {
+            self.register_shutdown_hook(
+                Subsystem::Policy,
+                Arc::new(shutdown_hooks::hook("policy_audit_log", move || {
+                    let path = path.clone();
+                    Box::pin(async move {
+                        let file = path.clone();
+                        tokio::task::spawn_blocking(move || std::fs::File::open(&file).and_then(|f| f.sync_all()))
+                            .await
+                            .map_err(|e| format!("Policy audit log sync task failed: {}", e))?
+                            .map_err(|e| format!("Failed to sync policy audit log {}: {}", path, e))
+                    })
+                })),
+            );
+        }
         info!("Policy engine initialized successfully");
         self.set_state(OrchestratorState::PolicyInitialized);
         Ok(())
     }
 
+    /// Publish the policy engine's ingestion quotas for the ingest server.
+    ///
+    /// Full mode writes ingest_component_quotas (ingest polls it); lite mode hands them to the
+    /// embedded ingest server at start. No policy engine means no quotas.
+    async fn publish_ingest_quotas(&mut self) -> Result<(), OrchestratorError> {
+        let Some(engine) = &self.policy_engine else {
+            return Ok(());
+        };
+        let quotas = engine.ingest_quotas().to_vec();
+        if let Some(db) = &self.db {
+            db.publish_ingest_quotas(&quotas)
+                .await
+                .map_err(OrchestratorError::DatabaseWriteFailed)?;
+        }
+        if let Some(runtime) = self.lite.as_mut() {
+            runtime.set_ingest_quotas(&quotas);
+        }
+        info!("Published {} ingest quota(s) from policy", quotas.len());
+        Ok(())
+    }
+
     /// Initialize event bus
     /// 
     /// FAIL-CLOSED: Returns error if bus certificates are missing
@@ -489,6 +1246,89 @@ impl Orchestrator {
         Ok(())
     }
 
+    /// Serve the status API and accept service heartbeats (before the slow startup steps, so
+    /// services can register while the orchestrator initializes).
+    async fn start_status_listener(&mut self) -> Result<(), OrchestratorError> {
+        let Some(addr) = self.registry_cfg.listen_addr else {
+            warn!("Orchestrator status listener DISABLED (RANSOMEYE_ORCHESTRATOR_STATUS_ADDR=off); service liveness is not tracked");
+            return Ok(());
+        };
+        let state = StatusState {
+            registry: self.registry.clone(),
+            token: self.registry_cfg.token.clone().map(Arc::new),
+            current_state: self.current_state.clone(),
+            config_drift_unhealthy: self.config_drift_unhealthy.clone(),
+            capacity: self.capacity.clone(),
+            failed_checks: self.failed_checks.clone(),
+        };
+        let task = service_registry::serve(addr, state)
+            .await
+            .map_err(OrchestratorError::ComponentInitFailed)?;
+        self.status_task = Some(task);
+        Ok(())
+    }
+
+    /// Serve /healthz, /readyz and /state for supervisors and load balancers (from environment
+    /// validation on, so probes see the startup progress).
+    async fn start_health_listener(&mut self) -> Result<(), OrchestratorError> {
+        let Some(addr) = self.health_addr else {
+            return Ok(());
+        };
+        let state = HealthState {
+            status: StatusState {
+                registry: self.registry.clone(),
+                token: self.registry_cfg.token.clone().map(Arc::new),
+                current_state: self.current_state.clone(),
+                config_drift_unhealthy: self.config_drift_unhealthy.clone(),
+                capacity: self.capacity.clone(),
+                failed_checks: self.failed_checks.clone(),
+            },
+            trail: self.audit_trail.clone(),
+            mode: self.mode.as_str(),
+        };
+        let task = health_server::serve(addr, state)
+            .await
+            .map_err(OrchestratorError::ComponentInitFailed)?;
+        self.health_task = Some(task);
+        Ok(())
+    }
+
+    /// Wait for a required service to report a serving heartbeat. Services are awaited in
+    /// dependency order under one deadline, started by the first service awaited.
+    ///
+    /// FAIL-CLOSED: Returns error if it is still missing after RANSOMEYE_SERVICE_REGISTRATION_WAIT_SECS
+    async fn await_service(
+        &self,
+        node: &Node,
+        deadline: &mut Option<tokio::time::Instant>,
+    ) -> Result<(), OrchestratorError> {
+        let deadline = *deadline.get_or_insert_with(|| {
+            info!(
+                "Waiting up to {}s for required services: {}",
+                self.registry_cfg.registration_wait.as_secs(),
+                self.registry.required().join(", ")
+            );
+            tokio::time::Instant::now() + self.registry_cfg.registration_wait
+        });
+        loop {
+            let missing = self.registry.missing_required(std::time::Instant::now());
+            if !missing.contains(&node.name) {
+                info!("Required service {} reporting ready", node.name);
+                return Ok(());
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(OrchestratorError::HealthGateFailed(format!(
+                    "Required service {} not ready after {}s (depends on {}); still missing: {}",
+                    node.name,
+                    self.registry_cfg.registration_wait.as_secs(),
+                    node.depends_on.join(", "),
+                    missing.join(", ")
+                )));
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+        }
+    }
+
     /// Health gate - verify all components report READY
     /// 
     /// FAIL-CLOSED: Returns error if any component is not ready
@@ -508,13 +1348,24 @@ impl Orchestrator {
             ));
         }
 
-        // Verify policy engine
-        if self.policy_engine.is_none() {
+        // Verify policy engine (lite mode may have explicitly skipped it)
+        if self.policy_engine.is_none() && !self.skip_policy() {
             return Err(OrchestratorError::HealthGateFailed(
                 "Policy engine missing".to_string()
             ));
         }
 
+        // Verify required services (separate binaries) are heartbeating; dry-run starts no listener
+        if !self.dry_run {
+            let missing = self.registry.missing_required(std::time::Instant::now());
+            if !missing.is_empty() {
+                return Err(OrchestratorError::HealthGateFailed(format!(
+                    "Required services not serving: {}",
+                    missing.join(", ")
+                )));
+            }
+        }
+
         info!("Health gate passed - all components READY");
         self.set_state(OrchestratorState::Ready);
         Ok(())
@@ -529,26 +1380,21 @@ impl Orchestrator {
             info!("DRY-RUN mode enabled");
         }
 
-        // Step 1: Environment validation
-        self.validate_environment()?;
-
-        // Step 2: Database initialization (MANDATORY - fail-closed)
-        self.initialize_database().await?;
-
-        // Step 3: Trust subsystem
-        self.initialize_trust()?;
-
-        // Step 4: Policy engine
-        self.initialize_policy()?;
-
-        // Step 5: Event bus
-        self.initialize_bus()?;
-
-        // Step 6: Core services
-        self.initialize_services()?;
-
-        // Step 7: Health gate
-        self.health_gate()?;
+        // Steps in dependency order (validated when the orchestrator was created)
+        let order: Vec<Node> = self.graph.startup_order()?.into_iter().cloned().collect();
+        info!(
+            "Startup order: {}",
+            order.iter().map(|n| n.name.as_str()).collect::<Vec<_>>().join(" -> ")
+        );
+        let mut services_deadline = None;
+        for node in &order {
+            match node.kind {
+                NodeKind::Subsystem => self.start_subsystem(node).await?,
+                // Dry-run starts no listener, so no service can report
+                NodeKind::Service if self.dry_run => {}
+                NodeKind::Service => self.await_service(node, &mut services_deadline).await?,
+            }
+        }
 
         // Transition to RUNNING
         self.set_state(OrchestratorState::Running);
@@ -556,7 +1402,7 @@ impl Orchestrator {
 
         // PROMPT-27: Only after successful final transition do we write RUNNING state to DB/audit.
         if let (Some(db), Some(component_id)) = (self.db.as_ref(), self.component_db_id) {
-            let _ = db
+            let health_id = db
                 .insert_component_health(
                     component_id,
                     "healthy",
@@ -570,7 +1416,9 @@ impl Orchestrator {
                 .await
                 .map_err(OrchestratorError::DatabaseWriteFailed)?;
 
-            let _ = db
+            self.note_health(health_id);
+
+            let audit_id = db
                 .insert_immutable_audit_log(
                     Some(component_id),
                     "orchestrator_startup",
@@ -584,12 +1432,133 @@ impl Orchestrator {
                 )
                 .await
                 .map_err(OrchestratorError::DatabaseWriteFailed)?;
+            self.note_audit("orchestrator_startup", audit_id);
+        }
+        if let Some(lite) = &self.lite {
+            let audit_id = lite
+                .audit(
+                    "orchestrator_startup",
+                    &serde_json::json!({"status": "RUNNING", "mode": "lite"}),
+                )
+                .await
+                .map_err(OrchestratorError::DatabaseWriteFailed)?;
+            self.note_audit("orchestrator_startup", audit_id);
         }
 
         info!("RansomEye Core Orchestrator started successfully");
         Ok(())
     }
 
+    fn subsystem(node: &Node) -> Result<Subsystem, OrchestratorError> {
+        Subsystem::from_name(&node.name).ok_or_else(|| {
+            OrchestratorError::ComponentInitFailed(format!("Unknown subsystem '{}' in the dependency graph", node.name))
+        })
+    }
+
+    /// Start one subsystem of the dependency graph.
+    async fn start_subsystem(&mut self, node: &Node) -> Result<(), OrchestratorError> {
+        match Self::subsystem(node)? {
+            Subsystem::Environment => self.validate_environment(),
+            Subsystem::StatusListener if self.dry_run => Ok(()),
+            Subsystem::StatusListener => self.start_status_listener().await,
+            Subsystem::HealthListener if self.dry_run => Ok(()),
+            Subsystem::HealthListener => self.start_health_listener().await,
+            // Database initialization (MANDATORY - fail-closed; lite mode uses the SQLite store)
+            Subsystem::Storage => match self.mode {
+                RunMode::Full => self.initialize_database().await,
+                RunMode::Lite => self.initialize_lite_storage().await,
+            },
+            Subsystem::Trust => self.initialize_trust(),
+            Subsystem::Policy => self.initialize_policy(),
+            Subsystem::IngestQuotas => self.publish_ingest_quotas().await,
+            Subsystem::Bus => self.initialize_bus(),
+            Subsystem::Services => self.initialize_services(),
+            // Lite mode embeds ingest instead of relying on a separate binary
+            Subsystem::EmbeddedIngest if self.mode == RunMode::Lite && !self.dry_run => self.start_embedded_ingest(),
+            // Lifecycle webhook delivery and config rollouts (Postgres control plane only)
+            Subsystem::WebhookDispatcher if self.mode == RunMode::Full && !self.dry_run => {
+                self.start_webhook_dispatcher().await
+            }
+            Subsystem::ConfigRollout if self.mode == RunMode::Full && !self.dry_run => self.start_config_rollout().await,
+            Subsystem::EmbeddedIngest | Subsystem::WebhookDispatcher | Subsystem::ConfigRollout => Ok(()),
+            Subsystem::HealthGate => self.health_gate(),
+        }
+    }
+
+    /// Stop one subsystem of the dependency graph.
+    fn stop_subsystem(&mut self, node: &Node) -> Result<(), OrchestratorError> {
+        match Self::subsystem(node)? {
+            // Services handle their own shutdown via signal handling; the embedded lite ingest is ours
+            Subsystem::EmbeddedIngest => {
+                if let Some(lite) = self.lite.as_mut() {
+                    lite.stop_ingest();
+                }
+            }
+            // Undelivered rows stay pending; an interrupted attempt is retried after its lease
+            Subsystem::WebhookDispatcher => {
+                if let Some(task) = self.webhook_task.take() {
+                    task.abort();
+                }
+            }
+            // Rollout transitions are single transactions; the next start resumes from the DB
+            Subsystem::ConfigRollout => {
+                if let Some(task) = self.rollout_task.take() {
+                    task.abort();
+                }
+            }
+            // Services see the orchestrator lost after their RANSOMEYE_ORCHESTRATOR_LOST_AFTER_SECS
+            Subsystem::StatusListener => {
+                if let Some(task) = self.status_task.take() {
+                    task.abort();
+                }
+            }
+            // Stopped after the later subsystems, so probes report SHUTTING_DOWN meanwhile
+            Subsystem::HealthListener => {
+                if let Some(task) = self.health_task.take() {
+                    task.abort();
+                }
+            }
+            Subsystem::Services => info!("Shutting down core services..."),
+            // Bus client handles its own cleanup
+            Subsystem::Bus if self.bus_client.is_some() => info!("Flushing event bus..."),
+            // Policy engine handles its own cleanup
+            Subsystem::Policy if self.policy_engine.is_some() => info!("Shutting down policy engine..."),
+            Subsystem::Trust if self.kernel.is_some() => info!("Shutting down trust subsystem..."),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn start_webhook_dispatcher(&mut self) -> Result<(), OrchestratorError> {
+        let cfg = webhook_dispatcher::WebhookDispatcherConfig::from_env()
+            .map_err(OrchestratorError::EnvironmentValidationFailed)?;
+        if !cfg.enabled {
+            warn!("Webhook delivery DISABLED (RANSOMEYE_WEBHOOKS_ENABLED=false); deliveries stay queued");
+            return Ok(());
+        }
+        let db_cfg = DbConfig::from_env_strict().map_err(OrchestratorError::EnvironmentValidationFailed)?;
+        let dispatcher = webhook_dispatcher::WebhookDispatcher::connect(cfg, &db_cfg)
+            .await
+            .map_err(OrchestratorError::DatabaseConnectionFailed)?;
+        self.webhook_task = Some(dispatcher.spawn());
+        Ok(())
+    }
+
+    async fn start_config_rollout(&mut self) -> Result<(), OrchestratorError> {
+        let cfg = config_rollout::RolloutConfig::from_env()
+            .map_err(OrchestratorError::EnvironmentValidationFailed)?;
+        if !cfg.enabled {
+            warn!("Config rollout controller DISABLED (RANSOMEYE_CONFIG_ROLLOUT_ENABLED=false); active rollouts are not promoted or rolled back");
+            return Ok(());
+        }
+        let db_cfg = DbConfig::from_env_strict().map_err(OrchestratorError::EnvironmentValidationFailed)?;
+        let controller = config_rollout::RolloutController::connect(cfg, &db_cfg)
+            .await
+            .map_err(OrchestratorError::DatabaseConnectionFailed)?;
+        self.rollout_task = Some(controller.spawn());
+        Ok(())
+    }
+
     /// Execute shutdown sequence (reverse of startup)
     /// 
     /// Orders shutdown to ensure graceful teardown
@@ -597,35 +1566,66 @@ impl Orchestrator {
         info!("Shutting down RansomEye Core Orchestrator...");
         self.set_state(OrchestratorState::ShuttingDown);
 
-        // Shutdown in reverse order of startup
-        
-        // Step 1: Shutdown core services (flush queues, persist state)
-        info!("Shutting down core services...");
-        // Services handle their own shutdown via signal handling
-        
-        // Step 2: Shutdown event bus (flush messages)
-        if let Some(bus) = &self.bus_client {
-            info!("Flushing event bus...");
-            // Bus client handles its own cleanup
+        // Reverse of the startup order; separately run services stop on their own. Each
+        // subsystem's hooks are awaited before it stops; a failed hook does not stop the others.
+        let order: Vec<Node> = self.graph.shutdown_order()?.into_iter().cloned().collect();
+        let mut incomplete = Vec::new();
+        let mut record_error = None;
+        for node in order.iter().filter(|n| n.kind == NodeKind::Subsystem) {
+            for hook in self.shutdown_hooks.for_subsystem(&node.name) {
+                let result = shutdown_hooks::run_hook(&node.name, hook, self.shutdown_cfg.hook_timeout).await;
+                if result.outcome.is_completed() {
+                    info!("Shutdown hook {}/{} completed in {} ms", result.subsystem, result.hook, result.elapsed_ms);
+                } else {
+                    let detail = match &result.outcome {
+                        HookOutcome::Failed(e) => e.clone(),
+                        _ => format!("no result within {} ms", result.timeout_ms),
+                    };
+                    error!("Shutdown hook {}/{} {}: {}", result.subsystem, result.hook, result.outcome.as_str(), detail);
+                    incomplete.push(format!("{}/{} ({})", result.subsystem, result.hook, result.outcome.as_str()));
+                }
+                if let Err(e) = self.record_shutdown_hook(&result).await {
+                    error!("Failed to record shutdown hook {}/{}: {}", result.subsystem, result.hook, e);
+                    record_error.get_or_insert(e);
+                }
+            }
+            self.stop_subsystem(node)?;
         }
 
-        // Step 3: Shutdown policy engine (persist state)
-        if let Some(policy) = &self.policy_engine {
-            info!("Shutting down policy engine...");
-            // Policy engine handles its own cleanup
+        self.state.store(false, Ordering::SeqCst);
+        if !incomplete.is_empty() {
+            return Err(OrchestratorError::ShutdownFailed(format!(
+                "Shutdown hook(s) did not complete: {}",
+                incomplete.join(", ")
+            )));
         }
-
-        // Step 4: Trust subsystem cleanup
-        if let Some(kernel) = &self.kernel {
-            info!("Shutting down trust subsystem...");
-            // Kernel cleanup
+        if let Some(e) = record_error {
+            return Err(OrchestratorError::DatabaseWriteFailed(e));
         }
-
-        self.state.store(false, Ordering::SeqCst);
         info!("RansomEye Core Orchestrator shutdown complete");
         Ok(())
     }
 
+    /// Record one hook result in component_health and immutable_audit_log (lite mode: audit only).
+    async fn record_shutdown_hook(&self, result: &HookResult) -> Result<(), String> {
+        let payload = serde_json::to_value(result).map_err(|e| format!("Failed to serialize hook result: {e}"))?;
+        if let (Some(db), Some(component_id)) = (self.db.as_ref(), self.component_db_id) {
+            let status = if result.outcome.is_completed() { HealthStatus::Healthy } else { HealthStatus::Degraded };
+            let health_id = db
+                .insert_component_health(component_id, status.as_str(), Some("shutdown_hook"), Some(&payload))
+                .await?;
+            self.note_health(health_id);
+            let audit_id = db
+                .insert_immutable_audit_log(Some(component_id), "orchestrator_shutdown_hook", "other", Some(component_id), &payload)
+                .await?;
+            self.note_audit("orchestrator_shutdown_hook", audit_id);
+        } else if let Some(lite) = &self.lite {
+            let audit_id = lite.audit("orchestrator_shutdown_hook", &payload).await?;
+            self.note_audit("orchestrator_shutdown_hook", audit_id);
+        }
+        Ok(())
+    }
+
     /// Check if orchestrator is running
     pub fn is_running(&self) -> bool {
         self.state.load(Ordering::SeqCst)
@@ -633,19 +1633,78 @@ impl Orchestrator {
 
     /// Run orchestrator (startup, wait for signal, shutdown)
     pub async fn run(&mut self) -> Result<(), OrchestratorError> {
-        // Startup
-        self.startup().await?;
+        // Startup (probes report FAILED until the process exits)
+        if let Err(e) = self.startup().await {
+            self.set_state(OrchestratorState::Failed);
+            return Err(e);
+        }
 
         if self.dry_run {
             info!("Dry-run complete - orchestrator initialized successfully");
             return Ok(());
         }
 
-        // Wait for shutdown signal
+        // Wait for shutdown signal; meanwhile check for config drift periodically and on reload (SIGHUP).
         info!("Orchestrator running - waiting for shutdown signal...");
-        signal::ctrl_c().await.map_err(|e| OrchestratorError::ShutdownFailed(
-            format!("Failed to wait for signal: {}", e)
-        ))?;
+        let mut reload = signal::unix::signal(signal::unix::SignalKind::hangup()).map_err(|e| {
+            OrchestratorError::ShutdownFailed(format!("Failed to install SIGHUP handler: {}", e))
+        })?;
+        let mut user1 = signal::unix::signal(signal::unix::SignalKind::user_defined1()).map_err(|e| {
+            OrchestratorError::ShutdownFailed(format!("Failed to install SIGUSR1 handler: {}", e))
+        })?;
+        let mut log_revert_at: Option<tokio::time::Instant> = None;
+        let drift_interval = std::time::Duration::from_secs(self.drift_cfg.check_interval_secs.max(1));
+        let mut drift_tick = tokio::time::interval_at(tokio::time::Instant::now() + drift_interval, drift_interval);
+        let liveness_interval = self.registry_cfg.check_interval;
+        let mut liveness_tick = tokio::time::interval(liveness_interval);
+        let track_liveness = self.status_task.is_some();
+        let capacity_interval = std::time::Duration::from_secs(self.capacity_cfg.sample_interval_secs.max(1));
+        let mut capacity_tick = tokio::time::interval(capacity_interval);
+        let track_capacity = self.capacity_cfg.sample_interval_secs > 0 && self.db.is_some();
+        if !track_capacity && self.db.is_some() {
+            warn!("Database capacity forecast DISABLED (RANSOMEYE_DB_CAPACITY_SAMPLE_SECS=0)");
+        }
+        loop {
+            tokio::select! {
+                res = signal::ctrl_c() => {
+                    // Either way the shutdown path below runs
+                    if let Err(e) = res {
+                        error!("Failed to wait for shutdown signal: {} - shutting down", e);
+                    }
+                    break;
+                }
+                _ = reload.recv() => {
+                    info!("Reload requested (SIGHUP) - re-checking configuration drift");
+                    let res = self.check_config_drift("reload").await;
+                    self.note_check("config_drift", res);
+                }
+                _ = drift_tick.tick(), if self.drift_cfg.check_interval_secs > 0 => {
+                    let res = self.check_config_drift("periodic").await;
+                    self.note_check("config_drift", res);
+                }
+                _ = liveness_tick.tick(), if track_liveness => {
+                    let res = self.check_service_liveness().await;
+                    self.note_check("service_liveness", res);
+                }
+                _ = capacity_tick.tick(), if track_capacity => {
+                    let res = self.check_capacity().await;
+                    self.note_check("db_capacity", res);
+                }
+                _ = user1.recv() => {
+                    let res = self.set_runtime_log_filter(true, "sigusr1").await;
+                    if self.note_check("log_override", res) == Some(true) {
+                        if let Some(ctl) = &self.log_override {
+                            log_revert_at = Some(tokio::time::Instant::now() + ctl.ttl);
+                        }
+                    }
+                }
+                _ = async { tokio::time::sleep_until(log_revert_at.unwrap_or_else(tokio::time::Instant::now)).await }, if log_revert_at.is_some() => {
+                    log_revert_at = None;
+                    let res = self.set_runtime_log_filter(false, "ttl_expired").await;
+                    self.note_check("log_override", res);
+                }
+            }
+        }
 
         // Shutdown
         self.shutdown().await?;
@@ -0,0 +1,200 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/orchestrator/src/lite.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Standalone single-node mode ("ransomeye lite") - embedded ingest server on the SQLite telemetry store, no Postgres/bus/policy-store services required
+
+/*
+ * Lite Mode
+ *
+ * RANSOMEYE_MODE=lite runs the orchestrator and the HTTP ingest server in one process on the
+ * SQLite lab store, for evaluation on a single machine. Everything that would silently weaken the
+ * full deployment stays explicit:
+ *   - Postgres is not used (no schema contract, no retention dry-run, no agent token or key pin
+ *     tables), so ingest must run with RANSOMEYE_INGEST_AUTH_MODE=disabled and
+ *     RANSOMEYE_INGEST_KEY_PINNING=disabled (ingest fails closed otherwise); event signatures
+ *     are verified against RANSOMEYE_TRUST_STORE_PATH/agent_keys only, and with no key files
+ *     there ingest must run with RANSOMEYE_INGEST_SIGNATURE_VERIFICATION=disabled
+ *   - the policy store is skipped only with RANSOMEYE_LITE_SKIP_POLICY=1
+ *   - the bus stays optional exactly as in full mode (skipped when no client cert is configured)
+ * Trust material (RANSOMEYE_ROOT_KEY_PATH, RANSOMEYE_TRUST_STORE_PATH) is still required.
+ */
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use sha2::{Digest, Sha256};
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use ingest::component_budget::ComponentQuota;
+use ingest::http_server::HttpIngestionServer;
+use ingest::runtime_controls::{RuntimeControlConfig, RuntimeControls};
+use ingest::storage::{self, AuditRecord, SqliteStore, StorageConfig, TelemetryStore};
+
+use kernel::otel::LogFilterHandle;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunMode {
+    /// Postgres + separate service binaries (default).
+    Full,
+    /// Single process, embedded ingest, SQLite store.
+    Lite,
+}
+
+impl RunMode {
+    /// RANSOMEYE_MODE value
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RunMode::Full => "full",
+            RunMode::Lite => "lite",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "full" => Ok(RunMode::Full),
+            "lite" => Ok(RunMode::Lite),
+            other => Err(format!("Invalid RANSOMEYE_MODE '{}' (expected full|lite)", other)),
+        }
+    }
+
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(&std::env::var("RANSOMEYE_MODE").unwrap_or_else(|_| "full".to_string()))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LiteConfig {
+    pub sqlite_path: PathBuf,
+    pub ingest_listen_addr: String,
+    /// Explicit opt-out of the signed policy store (RANSOMEYE_LITE_SKIP_POLICY=1).
+    pub skip_policy: bool,
+}
+
+impl LiteConfig {
+    pub fn from_env() -> Result<Self, String> {
+        // Same variable as the standalone ingest binary so both point at one file.
+        let storage = StorageConfig::from_env().map_err(|e| e.to_string())?;
+        let ingest_listen_addr = std::env::var("RANSOMEYE_INGESTION_LISTEN_ADDR")
+            .unwrap_or_else(|_| "127.0.0.1:8080".to_string());
+        let skip_policy = match std::env::var("RANSOMEYE_LITE_SKIP_POLICY") {
+            Ok(v) if v == "1" || v.eq_ignore_ascii_case("true") => true,
+            Ok(v) if v == "0" || v.eq_ignore_ascii_case("false") => false,
+            Ok(v) => return Err(format!("Invalid RANSOMEYE_LITE_SKIP_POLICY '{}' (expected 0|1)", v)),
+            Err(_) => false,
+        };
+        Ok(Self {
+            sqlite_path: storage.sqlite_path,
+            ingest_listen_addr,
+            skip_policy,
+        })
+    }
+}
+
+/// Store + embedded ingest task owned by the orchestrator in lite mode.
+pub struct LiteRuntime {
+    pub store: Arc<dyn TelemetryStore>,
+    /// Actor id for orchestrator audit rows (the SQLite store has a single fixed component).
+    pub component_id: Uuid,
+    ingest_task: Option<JoinHandle<()>>,
+    /// Policy-resolved ingestion quotas handed to the embedded ingest server at start.
+    ingest_quotas: Vec<ComponentQuota>,
+}
+
+impl LiteRuntime {
+    pub async fn open(cfg: &LiteConfig) -> Result<Self, String> {
+        let store: Arc<dyn TelemetryStore> = Arc::new(SqliteStore::open(&cfg.sqlite_path).map_err(|e| e.to_string())?);
+        let component_id = store.ingestion_component().await.map_err(|e| e.to_string())?;
+        info!("Lite mode storage ready: {}", cfg.sqlite_path.display());
+        Ok(Self {
+            store,
+            component_id,
+            ingest_task: None,
+            ingest_quotas: Vec::new(),
+        })
+    }
+
+    /// Start the ingest HTTP server in-process on the shared store.
+    /// `log_filter` lets the ingest runtime-config endpoint drive the orchestrator's log filter.
+    pub fn start_ingest(&mut self, cfg: &LiteConfig, log_filter: Option<(LogFilterHandle, String)>) -> Result<(), String> {
+        let (handle, baseline) = match log_filter {
+            Some((handle, baseline)) => (Some(handle), baseline),
+            None => (None, "info".to_string()),
+        };
+        let controls = Arc::new(RuntimeControls::new(handle, &baseline, RuntimeControlConfig::from_env()?)?);
+        let server = HttpIngestionServer::with_store(cfg.ingest_listen_addr.clone(), controls, self.store.clone(), None)
+            .map_err(|e| e.to_string())?
+            .with_component_quotas(self.ingest_quotas.clone());
+
+        let listen_addr = cfg.ingest_listen_addr.clone();
+        self.ingest_task = Some(tokio::spawn(async move {
+            if let Err(e) = server.start().await {
+                // Embedded ingest is part of the lite pipeline: losing it is fatal.
+                error!("FAIL-CLOSED: Embedded ingest server on {} stopped: {}", listen_addr, e);
+                std::process::exit(1);
+            }
+        }));
+        info!("Embedded ingest server started on {}", cfg.ingest_listen_addr);
+        Ok(())
+    }
+
+    /// Quotas resolved by the policy engine; there is no ingest_component_quotas table in lite mode.
+    pub fn set_ingest_quotas(&mut self, quotas: &[policy::IngestQuota]) {
+        self.ingest_quotas = quotas
+            .iter()
+            .map(|q| ComponentQuota {
+                component_type: q.component_type.clone(),
+                events_per_minute: q.events_per_minute,
+                policy_id: q.policy_id.clone(),
+                policy_version: q.policy_version.clone(),
+            })
+            .collect();
+    }
+
+    pub fn stop_ingest(&mut self) {
+        if let Some(task) = self.ingest_task.take() {
+            task.abort();
+            info!("Embedded ingest server stopped");
+        }
+    }
+
+    pub async fn audit(&self, action: &str, payload: &serde_json::Value) -> Result<Uuid, String> {
+        let payload_str = serde_json::to_string(payload)
+            .map_err(|e| format!("Failed to serialize audit payload JSON: {e}"))?;
+        let record = AuditRecord {
+            actor_component_id: Some(self.component_id),
+            actor_agent_id: None,
+            action: action.to_string(),
+            object_type: "other".to_string(),
+            object_id: Some(self.component_id),
+            event_time: Some(chrono::Utc::now()),
+            payload_json: payload.clone(),
+            payload_sha256: Sha256::digest(payload_str.as_bytes()).to_vec(),
+        };
+        storage::append_audit_standalone(self.store.as_ref(), &record)
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Loud, once-per-start notice of what lite mode does not provide.
+pub fn warn_lite_limitations(cfg: &LiteConfig) {
+    warn!("LITE MODE: single-node evaluation profile - not for production");
+    warn!("LITE MODE: Postgres schema contract, retention enforcement, agent token auth and key pinning are disabled");
+    if cfg.skip_policy {
+        warn!("LITE MODE: policy store skipped (RANSOMEYE_LITE_SKIP_POLICY=1)");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_mode_parse() {
+        assert_eq!(RunMode::parse("full").unwrap(), RunMode::Full);
+        assert_eq!(RunMode::parse("lite").unwrap(), RunMode::Lite);
+        assert!(RunMode::parse("LITE").is_err());
+        assert!(RunMode::parse("").is_err());
+    }
+}
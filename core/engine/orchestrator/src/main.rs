@@ -3,7 +3,7 @@
 // Details of functionality of this file: Main entrypoint for RansomEye Core Orchestrator - fail-closed lifecycle management
 
 use std::process;
-use tracing::{info, error};
+use tracing::{info, error, warn};
 
 // Import orchestrator library
 // Since this is a binary in the engine crate, we need to reference the module
@@ -15,11 +15,25 @@ use orchestrator::Orchestrator;
 
 #[tokio::main]
 async fn main() {
-    // Initialize tracing
-    tracing_subscriber::fmt::init();
+    orchestrator::BUILD_INFO.handle_version_flag("ransomeye_orchestrator");
+    // Initialize tracing (optional OTLP export)
+    let otel_guard = kernel::otel::init_tracing("ransomeye-orchestrator");
+    // Any panic (including inside a spawned task) dumps, reports and exits 70
+    kernel::crash::install("ransomeye-orchestrator", Some(orchestrator::crash_report::reporter()));
 
     info!("RansomEye Core Orchestrator starting...");
 
+    // FAIL-CLOSED: a release deployment only runs the exact build its release manifest lists
+    let build = &orchestrator::BUILD_INFO;
+    match build.verify_release_from_env("ransomeye_orchestrator") {
+        Ok(Some(release)) => info!("Build verified against release {} | commit={}", release, build.git_commit),
+        Ok(None) => warn!("RANSOMEYE_RELEASE_MANIFEST not set; build commit={} not verified against a release", build.git_commit),
+        Err(e) => {
+            error!("FAIL-CLOSED: {}", e);
+            process::exit(1);
+        }
+    }
+
     // Create orchestrator
     let mut orchestrator = match Orchestrator::new() {
         Ok(orch) => orch,
@@ -28,6 +42,10 @@ async fn main() {
             process::exit(1);
         }
     };
+    if let Err(e) = orchestrator.set_log_filter(otel_guard.log_filter(), otel_guard.baseline_filter()) {
+        error!("Failed to configure runtime log overrides: {}", e);
+        process::exit(1);
+    }
 
     // Run orchestrator (startup -> wait -> shutdown)
     match orchestrator.run().await {
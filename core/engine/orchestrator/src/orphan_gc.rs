@@ -0,0 +1,297 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/orchestrator/src/orphan_gc.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Orphaned derived-data garbage collection run after retention purges - removes mirror rows and marks derived rows whose source rows are gone, in bounded batches.
+
+use tracing::info;
+
+use super::db::CoreDb;
+use super::retention_enforcer::{held_row_predicate, hold_columns, QualifiedTable, RetentionEnforcerConfig};
+
+/// What happens to a derived row whose source is gone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrphanAction {
+    /// Pure copies of the source (nothing left to keep)
+    Delete,
+    /// Derived results with value of their own: stamped `source_expired_at`, kept for their own policy
+    MarkSourceExpired,
+}
+
+impl OrphanAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrphanAction::Delete => "delete",
+            OrphanAction::MarkSourceExpired => "mark_source_expired",
+        }
+    }
+}
+
+/// A derived table pointing at its source without a foreign key. FK-backed links cannot dangle:
+/// the enforcer keeps a source row while anything references it.
+#[derive(Debug, Clone, Copy)]
+pub struct DerivedLink {
+    pub name: &'static str,
+    pub derived_schema: &'static str,
+    pub derived_table: &'static str,
+    /// SQL over alias `d` yielding the source key (NULL = not linked)
+    pub derived_key: &'static str,
+    /// SQL over alias `d` restricting which derived rows this link covers
+    pub scope: &'static str,
+    pub source_schema: &'static str,
+    pub source_table: &'static str,
+    pub source_column: &'static str,
+    pub action: OrphanAction,
+}
+
+pub const DERIVED_LINKS: &[DerivedLink] = &[
+    DerivedLink {
+        name: "telemetry_stream_linux",
+        derived_schema: "public",
+        derived_table: "telemetry_events",
+        derived_key: "(d.event_data->>'telemetry_id')::uuid",
+        scope: "d.producer_type = 'linux_agent'",
+        source_schema: "ransomeye",
+        source_table: "linux_agent_telemetry",
+        source_column: "telemetry_id",
+        action: OrphanAction::Delete,
+    },
+    DerivedLink {
+        name: "telemetry_stream_windows",
+        derived_schema: "public",
+        derived_table: "telemetry_events",
+        derived_key: "(d.event_data->>'telemetry_id')::uuid",
+        scope: "d.producer_type = 'windows_agent'",
+        source_schema: "ransomeye",
+        source_table: "windows_agent_telemetry",
+        source_column: "telemetry_id",
+        action: OrphanAction::Delete,
+    },
+    DerivedLink {
+        name: "telemetry_stream_dpi",
+        derived_schema: "public",
+        derived_table: "telemetry_events",
+        derived_key: "(d.event_data->>'telemetry_id')::uuid",
+        scope: "d.producer_type = 'dpi_probe'",
+        source_schema: "ransomeye",
+        source_table: "dpi_probe_telemetry",
+        source_column: "telemetry_id",
+        action: OrphanAction::Delete,
+    },
+    DerivedLink {
+        name: "detection_correlation_run",
+        derived_schema: "ransomeye",
+        derived_table: "detection_results",
+        derived_key: "d.correlation_run_id",
+        scope: "TRUE",
+        source_schema: "ransomeye",
+        source_table: "correlation_graph",
+        source_column: "correlation_run_id",
+        action: OrphanAction::MarkSourceExpired,
+    },
+    DerivedLink {
+        name: "llm_request_correlation_run",
+        derived_schema: "ransomeye",
+        derived_table: "llm_requests",
+        derived_key: "d.correlation_run_id",
+        scope: "TRUE",
+        source_schema: "ransomeye",
+        source_table: "correlation_graph",
+        source_column: "correlation_run_id",
+        action: OrphanAction::MarkSourceExpired,
+    },
+];
+
+impl DerivedLink {
+    pub fn derived(&self) -> QualifiedTable {
+        QualifiedTable { schema: self.derived_schema.to_string(), table: self.derived_table.to_string() }
+    }
+
+    pub fn source(&self) -> QualifiedTable {
+        QualifiedTable { schema: self.source_schema.to_string(), table: self.source_table.to_string() }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OrphanGcResult {
+    pub link: &'static str,
+    pub derived: QualifiedTable,
+    pub source: QualifiedTable,
+    pub action: OrphanAction,
+    /// Set when the link was not evaluated (table absent in this deployment)
+    pub skipped_reason: Option<String>,
+    /// Orphans found (not yet marked, not under legal hold)
+    pub orphans_found: i64,
+    /// Orphans kept because they are linked to an active legal hold (delete links only)
+    pub held_rows_skipped: i64,
+    pub rows_deleted: i64,
+    pub rows_marked: i64,
+    pub batches_executed: i64,
+}
+
+/// SQL boolean over alias `d`: the derived row is linked and its source row is gone. For marking
+/// links, rows already marked are excluded.
+pub fn orphan_predicate(link: &DerivedLink) -> Result<String, String> {
+    let mut sql = format!(
+        "({scope}) AND ({key}) IS NOT NULL AND NOT EXISTS (SELECT 1 FROM {schema}.{table} s WHERE s.{col} = ({key}))",
+        scope = link.scope,
+        key = link.derived_key,
+        schema = QualifiedTable::quote_ident(link.source_schema)?,
+        table = QualifiedTable::quote_ident(link.source_table)?,
+        col = QualifiedTable::quote_ident(link.source_column)?
+    );
+    if link.action == OrphanAction::MarkSourceExpired {
+        sql.push_str(" AND d.source_expired_at IS NULL");
+    }
+    Ok(sql)
+}
+
+pub struct OrphanCollector {
+    cfg: RetentionEnforcerConfig,
+}
+
+impl OrphanCollector {
+    pub fn new(cfg: RetentionEnforcerConfig) -> Self {
+        Self { cfg }
+    }
+
+    /// One pass over every declared link. Dry runs only count.
+    #[tracing::instrument(name = "retention.orphan_gc", skip_all, fields(dry_run = dry_run))]
+    pub async fn collect(&self, db: &CoreDb, dry_run: bool) -> Result<Vec<OrphanGcResult>, String> {
+        let mut results = Vec::with_capacity(DERIVED_LINKS.len());
+        for link in DERIVED_LINKS {
+            results.push(self.collect_link(db, link, dry_run).await?);
+        }
+        Ok(results)
+    }
+
+    async fn collect_link(&self, db: &CoreDb, link: &DerivedLink, dry_run: bool) -> Result<OrphanGcResult, String> {
+        let derived = link.derived();
+        let source = link.source();
+        let mut result = OrphanGcResult {
+            link: link.name,
+            derived: derived.clone(),
+            source: source.clone(),
+            action: link.action,
+            skipped_reason: None,
+            orphans_found: 0,
+            held_rows_skipped: 0,
+            rows_deleted: 0,
+            rows_marked: 0,
+            batches_executed: 0,
+        };
+
+        for qt in [&derived, &source] {
+            if !self.table_exists(db, qt).await? {
+                result.skipped_reason = Some(format!("table {} does not exist", qt.as_fqn()));
+                return Ok(result);
+            }
+        }
+
+        let orphan = orphan_predicate(link)?;
+        let held = match link.action {
+            OrphanAction::Delete => held_row_predicate("d", &hold_columns(db, &derived).await?)?,
+            OrphanAction::MarkSourceExpired => "FALSE".to_string(),
+        };
+        let derived_fqn = format!(
+            "{}.{}",
+            QualifiedTable::quote_ident(&derived.schema)?,
+            QualifiedTable::quote_ident(&derived.table)?
+        );
+
+        let row = db
+            .client()
+            .query_one(
+                &format!(
+                    "SELECT COUNT(*) FILTER (WHERE NOT {held})::bigint, COUNT(*) FILTER (WHERE {held})::bigint \
+                     FROM {derived_fqn} d WHERE {orphan}"
+                ),
+                &[],
+            )
+            .await
+            .map_err(|e| format!("FAIL-CLOSED: Orphan count failed for {} ({}): {e}", derived.as_fqn(), link.name))?;
+        result.orphans_found = row.get(0);
+        result.held_rows_skipped = row.get(1);
+
+        info!(
+            "[RETENTION][ORPHAN-GC]{} {} orphan(s) in {} for source {} (action={})",
+            if dry_run { "[DRY-RUN]" } else { "" },
+            result.orphans_found,
+            derived.as_fqn(),
+            source.as_fqn(),
+            link.action.as_str()
+        );
+        if dry_run || result.orphans_found == 0 {
+            return Ok(result);
+        }
+
+        let sql = match link.action {
+            OrphanAction::Delete => format!(
+                "WITH batch AS (SELECT d.ctid FROM {derived_fqn} d WHERE {orphan} AND NOT {held} LIMIT $1) \
+                 DELETE FROM {derived_fqn} d USING batch WHERE d.ctid = batch.ctid"
+            ),
+            OrphanAction::MarkSourceExpired => format!(
+                "WITH batch AS (SELECT d.ctid FROM {derived_fqn} d WHERE {orphan} LIMIT $1) \
+                 UPDATE {derived_fqn} d SET source_expired_at = NOW() FROM batch WHERE d.ctid = batch.ctid"
+            ),
+        };
+
+        let mut changed: i64 = 0;
+        for _ in 0..self.cfg.max_batches_per_table {
+            let n = db
+                .client()
+                .execute(&sql, &[&self.cfg.batch_size])
+                .await
+                .map_err(|e| format!("FAIL-CLOSED: Orphan batch failed for {} ({}): {e}", derived.as_fqn(), link.name))?
+                as i64;
+            result.batches_executed += 1;
+            changed += n;
+            if n == 0 {
+                break;
+            }
+            if self.cfg.sleep_ms_between_batches > 0 {
+                tokio::time::sleep(std::time::Duration::from_millis(self.cfg.sleep_ms_between_batches as u64)).await;
+            }
+        }
+        match link.action {
+            OrphanAction::Delete => result.rows_deleted = changed,
+            OrphanAction::MarkSourceExpired => result.rows_marked = changed,
+        }
+        Ok(result)
+    }
+
+    async fn table_exists(&self, db: &CoreDb, qt: &QualifiedTable) -> Result<bool, String> {
+        let row = db
+            .client()
+            .query_one("SELECT to_regclass($1) IS NOT NULL", &[&qt.as_fqn()])
+            .await
+            .map_err(|e| format!("FAIL-CLOSED: Cannot resolve {}: {e}", qt.as_fqn()))?;
+        Ok(row.get(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{orphan_predicate, OrphanAction, DERIVED_LINKS};
+
+    #[test]
+    fn every_link_targets_allowed_schemas() {
+        for link in DERIVED_LINKS {
+            assert!(super::QualifiedTable::parse(&link.derived().as_fqn()).is_ok(), "{}", link.name);
+            assert!(super::QualifiedTable::parse(&link.source().as_fqn()).is_ok(), "{}", link.name);
+            assert!(orphan_predicate(link).is_ok(), "{}", link.name);
+        }
+    }
+
+    #[test]
+    fn predicate_checks_source_and_skips_marked_rows() {
+        let mirror = DERIVED_LINKS.iter().find(|l| l.name == "telemetry_stream_linux").unwrap();
+        let sql = orphan_predicate(mirror).unwrap();
+        assert!(sql.starts_with("(d.producer_type = 'linux_agent')"));
+        assert!(sql.contains(
+            "NOT EXISTS (SELECT 1 FROM \"ransomeye\".\"linux_agent_telemetry\" s WHERE s.\"telemetry_id\" = ((d.event_data->>'telemetry_id')::uuid))"
+        ));
+        assert!(!sql.contains("source_expired_at"));
+
+        let detections = DERIVED_LINKS.iter().find(|l| l.action == OrphanAction::MarkSourceExpired).unwrap();
+        assert!(orphan_predicate(detections).unwrap().ends_with(" AND d.source_expired_at IS NULL"));
+    }
+}
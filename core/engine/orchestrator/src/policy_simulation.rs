@@ -0,0 +1,373 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/orchestrator/src/policy_simulation.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Policy impact analysis - evaluates a candidate policy bundle (and optionally the deployed one) against a historical window of detections on a read-only session, reporting what would have been blocked or alerted.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, Utc};
+use policy::{
+    diff_decisions, transition_counts, DecisionChange, EvaluationContext, PolicyRef, PolicySimulator, SimulatedDecision,
+    SimulationSummary,
+};
+use ransomeye_core::AssetCatalog;
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use tracing::info;
+use uuid::Uuid;
+
+use super::asset_tags;
+use super::db::CoreDb;
+
+/// Alert ids kept per candidate outcome, and decision changes kept in the report.
+const SAMPLE_LIMIT: usize = 200;
+
+#[derive(Debug, Clone)]
+pub struct SimulationConfig {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub max_alerts: i64,
+    pub page_size: i64,
+}
+
+impl SimulationConfig {
+    /// Window defaults to the last 7 days.
+    pub fn new(from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>, max_alerts: i64) -> Result<Self, String> {
+        let to = to.unwrap_or_else(Utc::now);
+        let from = from.unwrap_or(to - Duration::days(7));
+        if from >= to {
+            return Err("FAIL-CLOSED: simulation window --from must be earlier than --to".to_string());
+        }
+        if max_alerts <= 0 {
+            return Err("FAIL-CLOSED: --max-alerts must be > 0".to_string());
+        }
+        Ok(Self { from, to, max_alerts, page_size: 1000 })
+    }
+}
+
+/// Detection row as read from ransomeye.detection_results (read-only).
+#[derive(Debug, Clone)]
+pub struct HistoricalDetection {
+    pub detection_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub detection_engine: String,
+    pub detection_name: String,
+    pub detection_category: Option<String>,
+    pub mitre_tactic: Option<String>,
+    pub mitre_technique: Option<String>,
+    pub severity: String,
+    pub confidence: f64,
+    pub score: Option<f64>,
+    pub artifacts: Option<JsonValue>,
+    /// entities.entity_key of the primary entity (hostname, IP or agent id)
+    pub entity_key: Option<String>,
+}
+
+/// Detection severity_level -> policy alert severity (critical/high/medium/low).
+pub fn alert_severity(severity_level: &str) -> &'static str {
+    match severity_level {
+        "critical" => "critical",
+        "error" => "high",
+        "warning" => "medium",
+        _ => "low",
+    }
+}
+
+/// MITRE ATT&CK tactic (id, slug or display name) -> kill chain stage used by policies.
+pub fn kill_chain_stage_for_tactic(tactic: &str) -> Option<&'static str> {
+    let t = tactic.trim().to_ascii_lowercase().replace([' ', '_'], "-");
+    let stage = match t.as_str() {
+        "ta0043" | "reconnaissance" => "reconnaissance",
+        "ta0042" | "resource-development" => "weaponization",
+        "ta0001" | "initial-access" => "delivery",
+        "ta0002" | "execution" | "ta0004" | "privilege-escalation" | "ta0006" | "credential-access" => "exploitation",
+        "ta0003" | "persistence" | "ta0005" | "defense-evasion" => "installation",
+        "ta0007" | "discovery" | "ta0008" | "lateral-movement" | "ta0011" | "command-and-control" => "command_control",
+        "ta0009" | "collection" | "ta0010" | "exfiltration" | "ta0040" | "impact" => "actions_on_objectives",
+        _ => return None,
+    };
+    Some(stage)
+}
+
+impl HistoricalDetection {
+    fn artifact_str(&self, key: &str) -> Option<String> {
+        self.artifacts
+            .as_ref()
+            .and_then(|a| a.get(key))
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string())
+    }
+
+    /// The context the live engine would have been handed for this detection. Stage and asset
+    /// class come from artifacts when the producer recorded them.
+    pub fn to_context(&self, catalog: &AssetCatalog) -> EvaluationContext {
+        let stage = self
+            .artifact_str("kill_chain_stage")
+            .or_else(|| self.mitre_tactic.as_deref().and_then(kill_chain_stage_for_tactic).map(str::to_string))
+            .or_else(|| self.detection_category.clone())
+            .unwrap_or_else(|| "unknown".to_string());
+        let asset_class = self.artifact_str("asset_class").or_else(|| {
+            self.entity_key
+                .as_deref()
+                .and_then(|k| catalog.lookup(k))
+                .map(|t| t.criticality.as_str().to_string())
+        });
+
+        let mut ctx = EvaluationContext::new(
+            &self.detection_id.to_string(),
+            alert_severity(&self.severity),
+            &stage,
+            asset_class,
+            self.entity_key.clone(),
+            &self.detection_engine,
+            vec![self.detection_name.clone()],
+            &format!("detection_results:{}", self.detection_id),
+            serde_json::json!({
+                "detection_name": self.detection_name,
+                "detection_category": self.detection_category,
+                "mitre_tactic": self.mitre_tactic,
+                "mitre_technique": self.mitre_technique,
+                "confidence": self.confidence,
+                "score": self.score,
+            }),
+        );
+        ctx.timestamp = self.created_at;
+        ctx
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BundleRun {
+    pub policies: Vec<PolicyRef>,
+    pub summary: SimulationSummary,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PolicySimulationReport {
+    pub run_id: Uuid,
+    pub generated_at: DateTime<Utc>,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub alerts_read: i64,
+    /// True when --max-alerts stopped the scan before the end of the window
+    pub truncated: bool,
+    pub candidate: BundleRun,
+    pub baseline: Option<BundleRun>,
+    /// First alert ids per candidate outcome
+    pub candidate_samples: BTreeMap<String, Vec<String>>,
+    pub changes_total: usize,
+    pub transitions: BTreeMap<String, usize>,
+    /// First SAMPLE_LIMIT changed decisions
+    pub changes: Vec<DecisionChange>,
+}
+
+impl PolicySimulationReport {
+    pub fn has_changes(&self) -> bool {
+        self.changes_total > 0
+    }
+}
+
+pub struct PolicySimulationRunner {
+    cfg: SimulationConfig,
+}
+
+impl PolicySimulationRunner {
+    pub fn new(cfg: SimulationConfig) -> Self {
+        Self { cfg }
+    }
+
+    /// Evaluate every detection in the window against the candidate (and baseline) bundle.
+    /// The session is switched to read-only first: simulation never writes decisions or anything else.
+    pub async fn run(
+        &self,
+        db: &CoreDb,
+        candidate: &PolicySimulator,
+        baseline: Option<&PolicySimulator>,
+    ) -> Result<PolicySimulationReport, String> {
+        db.client()
+            .batch_execute("SET SESSION CHARACTERISTICS AS TRANSACTION READ ONLY")
+            .await
+            .map_err(|e| format!("FAIL-CLOSED: Cannot switch simulation session to read-only: {e}"))?;
+
+        let catalog = asset_tags::load_catalog(db).await?;
+        let mut candidate_summary = SimulationSummary::default();
+        let mut baseline_summary = SimulationSummary::default();
+        let mut candidate_out: Vec<SimulatedDecision> = Vec::new();
+        let mut baseline_out: Vec<SimulatedDecision> = Vec::new();
+        let mut samples: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let mut alerts_read: i64 = 0;
+        let mut cursor: Option<(DateTime<Utc>, Uuid)> = None;
+        let mut truncated = false;
+
+        loop {
+            if alerts_read >= self.cfg.max_alerts {
+                truncated = !self.fetch_page(db, cursor, 1).await?.is_empty();
+                break;
+            }
+            let limit = self.cfg.page_size.min(self.cfg.max_alerts - alerts_read);
+            let page = self.fetch_page(db, cursor, limit).await?;
+            if page.is_empty() {
+                break;
+            }
+            cursor = page.last().map(|d| (d.created_at, d.detection_id));
+            alerts_read += page.len() as i64;
+
+            for det in &page {
+                let ctx = det.to_context(&catalog);
+                let decided = candidate.simulate(&ctx);
+                candidate_summary.record(&decided);
+                let bucket = samples.entry(decided.outcome.as_str().to_string()).or_default();
+                if bucket.len() < SAMPLE_LIMIT {
+                    bucket.push(decided.alert_id.clone());
+                }
+                if let Some(b) = baseline {
+                    let base = b.simulate(&ctx);
+                    baseline_summary.record(&base);
+                    baseline_out.push(base);
+                    candidate_out.push(decided);
+                }
+            }
+        }
+
+        let mut changes = diff_decisions(&baseline_out, &candidate_out);
+        let changes_total = changes.len();
+        let transitions = transition_counts(&changes);
+        changes.truncate(SAMPLE_LIMIT);
+
+        let report = PolicySimulationReport {
+            run_id: Uuid::new_v4(),
+            generated_at: Utc::now(),
+            from: self.cfg.from,
+            to: self.cfg.to,
+            alerts_read,
+            truncated,
+            candidate: BundleRun { policies: candidate.bundle(), summary: candidate_summary },
+            baseline: baseline.map(|b| BundleRun { policies: b.bundle(), summary: baseline_summary }),
+            candidate_samples: samples,
+            changes_total,
+            transitions,
+            changes,
+        };
+
+        info!(
+            "[POLICY-SIM] run_id={} alerts={} blocked={} alerted={} allowed={} ambiguous={} errors={} changed={}",
+            report.run_id,
+            alerts_read,
+            report.candidate.summary.blocked,
+            report.candidate.summary.alerted,
+            report.candidate.summary.allowed,
+            report.candidate.summary.ambiguous,
+            report.candidate.summary.errors,
+            changes_total
+        );
+        Ok(report)
+    }
+
+    async fn fetch_page(
+        &self,
+        db: &CoreDb,
+        after: Option<(DateTime<Utc>, Uuid)>,
+        limit: i64,
+    ) -> Result<Vec<HistoricalDetection>, String> {
+        // Keyset pagination on (created_at, detection_id) gives a stable, deterministic order.
+        let (after_ts, after_id) = match after {
+            Some((ts, id)) => (Some(ts), Some(id)),
+            None => (None, None),
+        };
+        let rows = db
+            .client()
+            .query(
+                r#"
+                SELECT d.detection_id, d.created_at, d.detection_engine, d.detection_name, d.detection_category,
+                       d.mitre_tactic, d.mitre_technique, d.severity::text, d.confidence, d.score, d.artifacts,
+                       e.entity_key
+                FROM ransomeye.detection_results d
+                LEFT JOIN ransomeye.entities e ON e.entity_id = d.primary_entity_id
+                WHERE d.created_at >= $1 AND d.created_at < $2
+                  AND ($3::timestamptz IS NULL OR (d.created_at, d.detection_id) > ($3, $4::uuid))
+                ORDER BY d.created_at ASC, d.detection_id ASC
+                LIMIT $5
+                "#,
+                &[&self.cfg.from, &self.cfg.to, &after_ts, &after_id, &limit],
+            )
+            .await
+            .map_err(|e| format!("FAIL-CLOSED: Cannot read ransomeye.detection_results for simulation: {e}"))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| HistoricalDetection {
+                detection_id: r.get(0),
+                created_at: r.get(1),
+                detection_engine: r.get(2),
+                detection_name: r.get(3),
+                detection_category: r.get(4),
+                mitre_tactic: r.get(5),
+                mitre_technique: r.get(6),
+                severity: r.get(7),
+                confidence: r.get(8),
+                score: r.get(9),
+                artifacts: r.get(10),
+                entity_key: r.get(11),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ransomeye_core::{AssetCriticality, AssetTags};
+
+    fn detection(tactic: Option<&str>, artifacts: Option<JsonValue>) -> HistoricalDetection {
+        HistoricalDetection {
+            detection_id: Uuid::from_u128(7),
+            created_at: DateTime::parse_from_rfc3339("2025-03-01T12:00:00Z").unwrap().with_timezone(&Utc),
+            detection_engine: "correlation".to_string(),
+            detection_name: "smb_fanout".to_string(),
+            detection_category: Some("lateral_movement".to_string()),
+            mitre_tactic: tactic.map(str::to_string),
+            mitre_technique: None,
+            severity: "error".to_string(),
+            confidence: 0.8,
+            score: None,
+            artifacts,
+            entity_key: Some("FS01.corp.example".to_string()),
+        }
+    }
+
+    #[test]
+    fn tactics_map_to_policy_stages() {
+        assert_eq!(kill_chain_stage_for_tactic("TA0008"), Some("command_control"));
+        assert_eq!(kill_chain_stage_for_tactic("Lateral Movement"), Some("command_control"));
+        assert_eq!(kill_chain_stage_for_tactic("privilege_escalation"), Some("exploitation"));
+        assert_eq!(kill_chain_stage_for_tactic("impact"), Some("actions_on_objectives"));
+        assert_eq!(kill_chain_stage_for_tactic("T1059"), None);
+        assert_eq!(alert_severity("error"), "high");
+        assert_eq!(alert_severity("info"), "low");
+    }
+
+    #[test]
+    fn context_prefers_recorded_stage_and_uses_asset_tags() {
+        let mut catalog = AssetCatalog::new();
+        catalog.insert_hostname(
+            "fs01",
+            AssetTags { criticality: AssetCriticality::Critical, owner: None, environment: None },
+        );
+
+        let ctx = detection(Some("lateral-movement"), None).to_context(&catalog);
+        assert_eq!(ctx.kill_chain_stage, "command_control");
+        assert_eq!(ctx.alert_severity, "high");
+        assert_eq!(ctx.asset_class.as_deref(), Some("critical"));
+        assert_eq!(ctx.timestamp.to_rfc3339(), "2025-03-01T12:00:00+00:00");
+        assert!(ctx.validate().is_ok());
+
+        let ctx = detection(Some("impact"), Some(serde_json::json!({"kill_chain_stage": "installation", "asset_class": "server"})))
+            .to_context(&catalog);
+        assert_eq!(ctx.kill_chain_stage, "installation");
+        assert_eq!(ctx.asset_class.as_deref(), Some("server"));
+
+        let ctx = detection(None, None).to_context(&AssetCatalog::new());
+        assert_eq!(ctx.kill_chain_stage, "lateral_movement");
+        assert!(ctx.asset_class.is_none());
+    }
+}
@@ -0,0 +1,135 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/orchestrator/src/policy_simulation_main.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Standalone policy simulation binary - evaluates a candidate signed policy bundle against historical detections and reports what would have been blocked or alerted; never writes enforcement decisions.
+
+use std::process;
+
+use chrono::{DateTime, Utc};
+use tracing::{error, info};
+
+#[path = "lib.rs"]
+mod orchestrator;
+
+use orchestrator::db::{CoreDb, DbConfig};
+use orchestrator::policy_simulation::{PolicySimulationRunner, SimulationConfig};
+use policy::PolicySimulator;
+
+fn usage_and_exit() -> ! {
+    eprintln!("RansomEye Policy Simulation");
+    eprintln!("");
+    eprintln!("USAGE:");
+    eprintln!("  ransomeye_policy_simulate --candidate <policy_dir> [--baseline <policy_dir>] [--from <rfc3339>] [--to <rfc3339>] [--max-alerts <n>]");
+    eprintln!("");
+    eprintln!("NOTES:");
+    eprintln!("  - Reads ransomeye.detection_results on a read-only session; no decision is written or audited.");
+    eprintln!("  - Window defaults to the 7 days before --to (default: now).");
+    eprintln!("  - --baseline (e.g. the deployed RANSOMEYE_POLICY_DIR) adds a per-alert decision diff.");
+    eprintln!("  - RANSOMEYE_TRUST_STORE_PATH is required (bundles are signature-verified like at startup).");
+    eprintln!("  - DB env vars are required: DB_HOST, DB_PORT, DB_NAME, DB_USER, DB_PASS");
+    eprintln!("  - Exit code 0 = no decision changes vs baseline (or no baseline), 3 = decision changes found");
+    process::exit(2);
+}
+
+fn arg_value(name: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter()
+        .position(|a| a == name)
+        .and_then(|i| args.get(i + 1).cloned())
+}
+
+fn arg_rfc3339(name: &str) -> Option<DateTime<Utc>> {
+    arg_value(name).map(|v| match DateTime::parse_from_rfc3339(&v) {
+        Ok(t) => t.with_timezone(&Utc),
+        Err(_) => usage_and_exit(),
+    })
+}
+
+fn load_bundle(path: &str, trust_store: &str) -> PolicySimulator {
+    match PolicySimulator::new(path, Some(trust_store)) {
+        Ok(s) => s,
+        Err(e) => {
+            error!("FAIL-CLOSED: Cannot load policy bundle {}: {}", path, e);
+            process::exit(1);
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    orchestrator::BUILD_INFO.handle_version_flag("ransomeye_policy_simulate");
+    tracing_subscriber::fmt().with_writer(std::io::stderr).init();
+
+    if std::env::args().any(|a| a == "--help" || a == "-h") {
+        usage_and_exit();
+    }
+    let Some(candidate_path) = arg_value("--candidate") else {
+        usage_and_exit();
+    };
+    let baseline_path = arg_value("--baseline");
+    let max_alerts = match arg_value("--max-alerts") {
+        None => 100_000,
+        Some(v) => v.parse::<i64>().unwrap_or_else(|_| usage_and_exit()),
+    };
+
+    let sim_cfg = match SimulationConfig::new(arg_rfc3339("--from"), arg_rfc3339("--to"), max_alerts) {
+        Ok(c) => c,
+        Err(e) => {
+            error!("{e}");
+            process::exit(1);
+        }
+    };
+
+    let trust_store = match std::env::var("RANSOMEYE_TRUST_STORE_PATH") {
+        Ok(p) => p,
+        Err(_) => {
+            error!("FAIL-CLOSED: RANSOMEYE_TRUST_STORE_PATH not set");
+            process::exit(1);
+        }
+    };
+    let candidate = load_bundle(&candidate_path, &trust_store);
+    let baseline = baseline_path.as_deref().map(|p| load_bundle(p, &trust_store));
+
+    let cfg = match DbConfig::from_env_strict() {
+        Ok(c) => c,
+        Err(e) => {
+            error!("{e}");
+            process::exit(1);
+        }
+    };
+
+    let db = match CoreDb::connect_strict(&cfg).await {
+        Ok(db) => db,
+        Err(e) => {
+            error!("FAIL-CLOSED: DB connect failed: {e}");
+            process::exit(1);
+        }
+    };
+
+    info!(
+        "Policy simulation starting (candidate={}, baseline={}, from={}, to={}, max_alerts={})",
+        candidate_path,
+        baseline_path.as_deref().unwrap_or("none"),
+        sim_cfg.from.to_rfc3339(),
+        sim_cfg.to.to_rfc3339(),
+        sim_cfg.max_alerts
+    );
+
+    let report = match PolicySimulationRunner::new(sim_cfg).run(&db, &candidate, baseline.as_ref()).await {
+        Ok(r) => r,
+        Err(e) => {
+            error!("{e}");
+            process::exit(1);
+        }
+    };
+
+    // Full report to stdout (machine-readable); summary already logged by the runner.
+    match serde_json::to_string_pretty(&report) {
+        Ok(s) => println!("{s}"),
+        Err(e) => {
+            error!("Failed to serialize policy simulation report: {e}");
+            process::exit(1);
+        }
+    }
+
+    process::exit(if report.has_changes() { 3 } else { 0 });
+}
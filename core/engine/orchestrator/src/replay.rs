@@ -0,0 +1,719 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/orchestrator/src/replay.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Deterministic replay of stored raw_events through normalization/correlation/detection in a sandbox schema, reporting detection diffs between rule-pack versions.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sha2::{Digest, Sha256};
+use tracing::info;
+use uuid::Uuid;
+
+use ransomeye_core::input::validated_events::{ValidatedEvent, ValidationMetadata};
+use ransomeye_core::{AssetTagEnricher, CorrelationEngine, DetectionResult, EngineConfig};
+
+use super::asset_tags;
+use super::db::CoreDb;
+use super::retention_enforcer::QualifiedTable;
+
+/// Schemas that replay must never write into (production data).
+const PROTECTED_SCHEMAS: &[&str] = &["ransomeye", "public", "pg_catalog", "information_schema"];
+
+const REPLAY_VALIDATOR_VERSION: &str = "replay-1.0.0";
+
+/// Detection rule pack: a versioned set of correlation engine tunables.
+///
+/// Fields not present in the JSON file fall back to `EngineConfig::default()`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RulePack {
+    pub version: String,
+    #[serde(default)]
+    pub min_confidence_threshold: Option<f64>,
+    #[serde(default)]
+    pub temporal_window_seconds: Option<u64>,
+    #[serde(default)]
+    pub max_events_per_window: Option<usize>,
+    #[serde(default)]
+    pub entity_ttl_seconds: Option<u64>,
+    #[serde(default)]
+    pub min_signal_set: Option<Vec<String>>,
+}
+
+impl RulePack {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let raw = std::fs::read_to_string(path)
+            .map_err(|e| format!("FAIL-CLOSED: Cannot read rule pack {}: {e}", path.display()))?;
+        Self::parse(&raw).map_err(|e| format!("{e} ({})", path.display()))
+    }
+
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let pack: RulePack = serde_json::from_str(raw)
+            .map_err(|e| format!("FAIL-CLOSED: Invalid rule pack JSON: {e}"))?;
+        if pack.version.trim().is_empty() {
+            return Err("FAIL-CLOSED: rule pack 'version' must be non-empty".to_string());
+        }
+        if let Some(t) = pack.min_confidence_threshold {
+            if !(0.0..=1.0).contains(&t) {
+                return Err(format!(
+                    "FAIL-CLOSED: rule pack min_confidence_threshold must be within [0.0, 1.0] (got {t})"
+                ));
+            }
+        }
+        Ok(pack)
+    }
+
+    pub fn engine_config(&self) -> EngineConfig {
+        let mut cfg = EngineConfig::default();
+        if let Some(v) = self.min_confidence_threshold {
+            cfg.min_confidence_threshold = v;
+        }
+        if let Some(v) = self.temporal_window_seconds {
+            cfg.temporal_window_seconds = v;
+        }
+        if let Some(v) = self.max_events_per_window {
+            cfg.max_events_per_window = v;
+        }
+        if let Some(v) = self.entity_ttl_seconds {
+            cfg.entity_ttl_seconds = v;
+        }
+        if let Some(v) = &self.min_signal_set {
+            cfg.min_signal_set = v.iter().cloned().collect::<HashSet<String>>();
+        }
+        cfg
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplayConfig {
+    pub sandbox_schema: String,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub max_events: i64,
+    pub page_size: i64,
+}
+
+impl ReplayConfig {
+    pub fn from_env() -> Result<Self, String> {
+        let sandbox_schema = std::env::var("RANSOMEYE_REPLAY_SANDBOX_SCHEMA")
+            .unwrap_or_else(|_| "ransomeye_replay".to_string());
+        validate_sandbox_schema(&sandbox_schema)?;
+
+        let from = env_rfc3339("RANSOMEYE_REPLAY_FROM")?;
+        let to = env_rfc3339("RANSOMEYE_REPLAY_TO")?;
+        if let (Some(f), Some(t)) = (from, to) {
+            if f >= t {
+                return Err("FAIL-CLOSED: RANSOMEYE_REPLAY_FROM must be earlier than RANSOMEYE_REPLAY_TO".to_string());
+            }
+        }
+
+        let max_events = env_i64("RANSOMEYE_REPLAY_MAX_EVENTS", 100_000)?;
+        if max_events <= 0 {
+            return Err("FAIL-CLOSED: RANSOMEYE_REPLAY_MAX_EVENTS must be > 0".to_string());
+        }
+        let page_size = env_i64("RANSOMEYE_REPLAY_PAGE_SIZE", 1000)?;
+        if page_size <= 0 {
+            return Err("FAIL-CLOSED: RANSOMEYE_REPLAY_PAGE_SIZE must be > 0".to_string());
+        }
+
+        Ok(Self {
+            sandbox_schema,
+            from,
+            to,
+            max_events,
+            page_size,
+        })
+    }
+}
+
+/// Raw event row as read from ransomeye.raw_events (read-only).
+#[derive(Debug, Clone)]
+pub struct ReplayRawEvent {
+    pub raw_event_id: Uuid,
+    pub source_type: String,
+    pub source_agent_id: Option<Uuid>,
+    pub observed_at: Option<DateTime<Utc>>,
+    pub received_at: DateTime<Utc>,
+    /// None for rows ingest stored as a summary (payload_storage = 'summary'); those are skipped
+    pub payload_json: Option<JsonValue>,
+}
+
+/// Normalized form of a replayed event (mirrors core/normalization_worker/normalize.py).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayNormalizedEvent {
+    pub raw_event_id: Uuid,
+    pub source_type: String,
+    pub entity_id: String,
+    pub event_kind: String,
+    pub event_subkind: Option<String>,
+    pub observed_at: DateTime<Utc>,
+    pub deterministic_key: Vec<u8>,
+}
+
+/// Extract event_kind with the same precedence as the normalization worker.
+pub fn extract_event_kind(payload: &JsonValue) -> Option<String> {
+    let data = payload.get("data");
+    data.and_then(|d| d.get("event_category"))
+        .and_then(|v| v.as_str())
+        .or_else(|| payload.get("event_type").and_then(|v| v.as_str()))
+        .or_else(|| {
+            data.and_then(|d| d.get("features"))
+                .and_then(|f| f.get("event_type"))
+                .and_then(|v| v.as_str())
+        })
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+}
+
+/// Deterministic normalization of a raw event. Events without an event_kind are skipped (None),
+/// matching the fail-closed behaviour of the normalization worker for individual rows.
+pub fn normalize_raw_event(raw: &ReplayRawEvent) -> Option<ReplayNormalizedEvent> {
+    let payload = raw.payload_json.as_ref()?;
+    let event_kind = extract_event_kind(payload)?;
+    let event_subkind = payload
+        .get("data")
+        .and_then(|d| d.get("features"))
+        .and_then(|f| f.get("event_type"))
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+
+    // Event time, never processing time: fall back to received_at when the emitter gave none.
+    let observed_at = raw.observed_at.unwrap_or(raw.received_at);
+    let entity_id = raw
+        .source_agent_id
+        .map(|a| a.to_string())
+        .unwrap_or_else(|| format!("unattributed:{}", raw.source_type));
+
+    let key_data = format!(
+        "{}|{}|{}|{}",
+        raw.raw_event_id,
+        raw.source_type,
+        event_kind,
+        observed_at.to_rfc3339()
+    );
+    let mut hasher = Sha256::new();
+    hasher.update(key_data.as_bytes());
+
+    Some(ReplayNormalizedEvent {
+        raw_event_id: raw.raw_event_id,
+        source_type: raw.source_type.clone(),
+        entity_id,
+        event_kind,
+        event_subkind,
+        observed_at,
+        deterministic_key: hasher.finalize().to_vec(),
+    })
+}
+
+fn to_validated_event(ev: &ReplayNormalizedEvent, payload: Option<&JsonValue>) -> ValidatedEvent {
+    let mut map: HashMap<String, JsonValue> = HashMap::new();
+    if let Some(JsonValue::Object(obj)) = payload.and_then(|p| p.get("data")) {
+        for (k, v) in obj {
+            map.insert(k.clone(), v.clone());
+        }
+    }
+    ValidatedEvent {
+        event_id: ev.raw_event_id.to_string(),
+        entity_id: ev.entity_id.clone(),
+        timestamp: ev.observed_at,
+        signal_type: ev.event_kind.clone(),
+        payload: map,
+        validation_metadata: ValidationMetadata {
+            // Replay must be deterministic: validation time is pinned to event time.
+            validated_at: ev.observed_at,
+            validator_version: REPLAY_VALIDATOR_VERSION.to_string(),
+            checks_passed: vec!["replay_normalization".to_string()],
+            validation_hash: Some(hex_lower(&ev.deterministic_key)),
+        },
+    }
+}
+
+/// Detection outcome for one raw event under one rule pack (diff key = raw_event_id).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReplayDetection {
+    pub raw_event_id: Uuid,
+    pub entity_id: String,
+    pub stage: String,
+    pub confidence: f64,
+    /// Confidence weighted by asset criticality
+    pub priority: f64,
+    pub asset_criticality: Option<String>,
+}
+
+impl ReplayDetection {
+    fn from_result(raw_event_id: Uuid, d: &DetectionResult) -> Self {
+        Self {
+            raw_event_id,
+            entity_id: d.entity_id.clone(),
+            stage: d.kill_chain_stage.name().to_string(),
+            confidence: d.confidence,
+            priority: d.enrichment.priority,
+            asset_criticality: d.enrichment.asset.as_ref().map(|a| a.criticality.as_str().to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DetectionChange {
+    pub raw_event_id: Uuid,
+    pub entity_id: String,
+    pub baseline_stage: String,
+    pub candidate_stage: String,
+    pub baseline_confidence: f64,
+    pub candidate_confidence: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DetectionDiff {
+    pub added: Vec<ReplayDetection>,
+    pub removed: Vec<ReplayDetection>,
+    pub changed: Vec<DetectionChange>,
+    pub unchanged: usize,
+}
+
+impl DetectionDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Confidence deltas below this are treated as equal (floating point noise).
+const CONFIDENCE_EPSILON: f64 = 1e-9;
+
+/// Compute the detection diff between two rule-pack runs keyed by raw_event_id.
+pub fn diff_detections(baseline: &[ReplayDetection], candidate: &[ReplayDetection]) -> DetectionDiff {
+    let base: BTreeMap<Uuid, &ReplayDetection> = baseline.iter().map(|d| (d.raw_event_id, d)).collect();
+    let cand: BTreeMap<Uuid, &ReplayDetection> = candidate.iter().map(|d| (d.raw_event_id, d)).collect();
+
+    let mut diff = DetectionDiff::default();
+    for (id, b) in &base {
+        match cand.get(id) {
+            None => diff.removed.push((*b).clone()),
+            Some(c) => {
+                if b.stage != c.stage || (b.confidence - c.confidence).abs() > CONFIDENCE_EPSILON {
+                    diff.changed.push(DetectionChange {
+                        raw_event_id: *id,
+                        entity_id: b.entity_id.clone(),
+                        baseline_stage: b.stage.clone(),
+                        candidate_stage: c.stage.clone(),
+                        baseline_confidence: b.confidence,
+                        candidate_confidence: c.confidence,
+                    });
+                } else {
+                    diff.unchanged += 1;
+                }
+            }
+        }
+    }
+    for (id, c) in &cand {
+        if !base.contains_key(id) {
+            diff.added.push((*c).clone());
+        }
+    }
+    diff
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayReport {
+    pub run_id: Uuid,
+    pub sandbox_schema: String,
+    pub baseline_version: String,
+    pub candidate_version: String,
+    pub events_read: i64,
+    pub events_normalized: i64,
+    pub events_skipped: i64,
+    pub baseline_detections: usize,
+    pub candidate_detections: usize,
+    pub diff: DetectionDiff,
+}
+
+pub struct ReplayRunner {
+    cfg: ReplayConfig,
+}
+
+impl ReplayRunner {
+    pub fn new(cfg: ReplayConfig) -> Self {
+        Self { cfg }
+    }
+
+    /// Replay raw_events through both rule packs and persist normalized events, detections,
+    /// and the run report into the sandbox schema only. Production tables are read, never written.
+    pub async fn run(
+        &self,
+        db: &CoreDb,
+        baseline: &RulePack,
+        candidate: &RulePack,
+    ) -> Result<ReplayReport, String> {
+        let run_id = Uuid::new_v4();
+        let schema_q = QualifiedTable::quote_ident(&self.cfg.sandbox_schema)?;
+        self.prepare_sandbox(db, &schema_q).await?;
+
+        // Both packs see the same asset tags, so priority differs only through confidence
+        let enricher = Arc::new(AssetTagEnricher::new(asset_tags::load_catalog(db).await?));
+        let baseline_engine = CorrelationEngine::new(baseline.engine_config()).with_enricher(enricher.clone());
+        let candidate_engine = CorrelationEngine::new(candidate.engine_config()).with_enricher(enricher);
+
+        let mut baseline_out: Vec<ReplayDetection> = Vec::new();
+        let mut candidate_out: Vec<ReplayDetection> = Vec::new();
+        let mut events_read: i64 = 0;
+        let mut events_normalized: i64 = 0;
+        let mut events_skipped: i64 = 0;
+        let mut cursor: Option<(DateTime<Utc>, Uuid)> = None;
+
+        while events_read < self.cfg.max_events {
+            let limit = self.cfg.page_size.min(self.cfg.max_events - events_read);
+            let page = self.fetch_page(db, cursor, limit).await?;
+            if page.is_empty() {
+                break;
+            }
+            cursor = page.last().map(|r| (r.received_at, r.raw_event_id));
+            events_read += page.len() as i64;
+
+            for raw in &page {
+                let Some(ev) = normalize_raw_event(raw) else {
+                    events_skipped += 1;
+                    continue;
+                };
+                events_normalized += 1;
+                self.insert_normalized(db, &schema_q, run_id, &ev).await?;
+
+                let validated = to_validated_event(&ev, raw.payload_json.as_ref());
+                for (pack, engine, out) in [
+                    (baseline, &baseline_engine, &mut baseline_out),
+                    (candidate, &candidate_engine, &mut candidate_out),
+                ] {
+                    // Invariant violations are a detection outcome of the rule pack, not a replay failure.
+                    if let Ok(Some(d)) = engine.process_event(validated.clone()) {
+                        let det = ReplayDetection::from_result(ev.raw_event_id, &d);
+                        self.insert_detection(db, &schema_q, run_id, &pack.version, &det).await?;
+                        out.push(det);
+                    }
+                }
+            }
+        }
+
+        let diff = diff_detections(&baseline_out, &candidate_out);
+        let report = ReplayReport {
+            run_id,
+            sandbox_schema: self.cfg.sandbox_schema.clone(),
+            baseline_version: baseline.version.clone(),
+            candidate_version: candidate.version.clone(),
+            events_read,
+            events_normalized,
+            events_skipped,
+            baseline_detections: baseline_out.len(),
+            candidate_detections: candidate_out.len(),
+            diff,
+        };
+        self.insert_report(db, &schema_q, &report).await?;
+
+        info!(
+            "[REPLAY] run_id={} read={} normalized={} skipped={} added={} removed={} changed={}",
+            run_id,
+            events_read,
+            events_normalized,
+            events_skipped,
+            report.diff.added.len(),
+            report.diff.removed.len(),
+            report.diff.changed.len()
+        );
+        Ok(report)
+    }
+
+    async fn prepare_sandbox(&self, db: &CoreDb, schema_q: &str) -> Result<(), String> {
+        let ddl = format!(
+            r#"
+            CREATE SCHEMA IF NOT EXISTS {s};
+            CREATE TABLE IF NOT EXISTS {s}.replay_runs (
+              run_id                 uuid PRIMARY KEY,
+              created_at             timestamptz NOT NULL DEFAULT now(),
+              baseline_version       text NOT NULL,
+              candidate_version      text NOT NULL,
+              report_json            jsonb NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS {s}.replay_normalized_events (
+              run_id                 uuid NOT NULL,
+              raw_event_id           uuid NOT NULL,
+              observed_at            timestamptz NOT NULL,
+              source_type            text NOT NULL,
+              entity_id              text NOT NULL,
+              event_kind             text NOT NULL,
+              event_subkind          text NULL,
+              deterministic_key      bytea NOT NULL,
+              PRIMARY KEY (run_id, raw_event_id)
+            );
+            CREATE TABLE IF NOT EXISTS {s}.replay_detections (
+              run_id                 uuid NOT NULL,
+              rule_pack_version      text NOT NULL,
+              raw_event_id           uuid NOT NULL,
+              entity_id              text NOT NULL,
+              kill_chain_stage       text NOT NULL,
+              confidence             double precision NOT NULL,
+              priority               double precision NULL,
+              asset_criticality      text NULL,
+              PRIMARY KEY (run_id, rule_pack_version, raw_event_id)
+            );
+            ALTER TABLE {s}.replay_detections ADD COLUMN IF NOT EXISTS priority double precision NULL;
+            ALTER TABLE {s}.replay_detections ADD COLUMN IF NOT EXISTS asset_criticality text NULL;
+            "#,
+            s = schema_q
+        );
+        db.client()
+            .batch_execute(&ddl)
+            .await
+            .map_err(|e| format!("FAIL-CLOSED: Cannot prepare replay sandbox schema: {e}"))
+    }
+
+    async fn fetch_page(
+        &self,
+        db: &CoreDb,
+        after: Option<(DateTime<Utc>, Uuid)>,
+        limit: i64,
+    ) -> Result<Vec<ReplayRawEvent>, String> {
+        // Keyset pagination on (received_at, raw_event_id) gives a stable, deterministic order.
+        let (after_ts, after_id) = match after {
+            Some((ts, id)) => (Some(ts), Some(id)),
+            None => (None, None),
+        };
+        let rows = db
+            .client()
+            .query(
+                r#"
+                SELECT raw_event_id, source_type::text, source_agent_id, observed_at, received_at,
+                       CASE WHEN payload_storage = 'full' THEN payload_json END
+                FROM ransomeye.raw_events
+                WHERE ($1::timestamptz IS NULL OR received_at >= $1)
+                  AND ($2::timestamptz IS NULL OR received_at < $2)
+                  AND ($3::timestamptz IS NULL OR (received_at, raw_event_id) > ($3, $4::uuid))
+                ORDER BY received_at ASC, raw_event_id ASC
+                LIMIT $5
+                "#,
+                &[&self.cfg.from, &self.cfg.to, &after_ts, &after_id, &limit],
+            )
+            .await
+            .map_err(|e| format!("FAIL-CLOSED: Cannot read ransomeye.raw_events for replay: {e}"))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| ReplayRawEvent {
+                raw_event_id: r.get(0),
+                source_type: r.get(1),
+                source_agent_id: r.get(2),
+                observed_at: r.get(3),
+                received_at: r.get(4),
+                payload_json: r.get(5),
+            })
+            .collect())
+    }
+
+    async fn insert_normalized(
+        &self,
+        db: &CoreDb,
+        schema_q: &str,
+        run_id: Uuid,
+        ev: &ReplayNormalizedEvent,
+    ) -> Result<(), String> {
+        let sql = format!(
+            r#"
+            INSERT INTO {s}.replay_normalized_events (
+                run_id, raw_event_id, observed_at, source_type, entity_id, event_kind, event_subkind, deterministic_key
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+            s = schema_q
+        );
+        db.client()
+            .execute(
+                &sql,
+                &[
+                    &run_id,
+                    &ev.raw_event_id,
+                    &ev.observed_at,
+                    &ev.source_type,
+                    &ev.entity_id,
+                    &ev.event_kind,
+                    &ev.event_subkind,
+                    &ev.deterministic_key,
+                ],
+            )
+            .await
+            .map_err(|e| format!("FAIL-CLOSED: Cannot write replay_normalized_events: {e}"))?;
+        Ok(())
+    }
+
+    async fn insert_detection(
+        &self,
+        db: &CoreDb,
+        schema_q: &str,
+        run_id: Uuid,
+        rule_pack_version: &str,
+        det: &ReplayDetection,
+    ) -> Result<(), String> {
+        let sql = format!(
+            r#"
+            INSERT INTO {s}.replay_detections (
+                run_id, rule_pack_version, raw_event_id, entity_id, kill_chain_stage, confidence,
+                priority, asset_criticality
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+            s = schema_q
+        );
+        db.client()
+            .execute(
+                &sql,
+                &[
+                    &run_id,
+                    &rule_pack_version,
+                    &det.raw_event_id,
+                    &det.entity_id,
+                    &det.stage,
+                    &det.confidence,
+                    &det.priority,
+                    &det.asset_criticality,
+                ],
+            )
+            .await
+            .map_err(|e| format!("FAIL-CLOSED: Cannot write replay_detections: {e}"))?;
+        Ok(())
+    }
+
+    async fn insert_report(&self, db: &CoreDb, schema_q: &str, report: &ReplayReport) -> Result<(), String> {
+        let report_json = serde_json::to_value(report)
+            .map_err(|e| format!("Failed to serialize replay report: {e}"))?;
+        let sql = format!(
+            r#"
+            INSERT INTO {s}.replay_runs (run_id, baseline_version, candidate_version, report_json)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            s = schema_q
+        );
+        db.client()
+            .execute(
+                &sql,
+                &[
+                    &report.run_id,
+                    &report.baseline_version,
+                    &report.candidate_version,
+                    &report_json,
+                ],
+            )
+            .await
+            .map_err(|e| format!("FAIL-CLOSED: Cannot write replay_runs: {e}"))?;
+        Ok(())
+    }
+}
+
+/// Fail-closed: the sandbox schema must be a plain identifier and never a production schema.
+pub fn validate_sandbox_schema(schema: &str) -> Result<(), String> {
+    QualifiedTable::quote_ident(schema)?;
+    if PROTECTED_SCHEMAS.contains(&schema.to_lowercase().as_str()) {
+        return Err(format!(
+            "FAIL-CLOSED: Replay sandbox schema '{schema}' is a production schema (forbidden: {})",
+            PROTECTED_SCHEMAS.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+fn hex_lower(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn env_i64(key: &str, default_value: i64) -> Result<i64, String> {
+    match std::env::var(key) {
+        Ok(v) => v
+            .parse::<i64>()
+            .map_err(|e| format!("FAIL-CLOSED: Invalid {key}='{v}': {e}")),
+        Err(_) => Ok(default_value),
+    }
+}
+
+fn env_rfc3339(key: &str) -> Result<Option<DateTime<Utc>>, String> {
+    match std::env::var(key) {
+        Ok(v) => DateTime::parse_from_rfc3339(&v)
+            .map(|t| Some(t.with_timezone(&Utc)))
+            .map_err(|e| format!("FAIL-CLOSED: Invalid {key}='{v}' (expected RFC3339): {e}")),
+        Err(_) => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw(payload: JsonValue) -> ReplayRawEvent {
+        ReplayRawEvent {
+            raw_event_id: Uuid::nil(),
+            source_type: "linux_agent".to_string(),
+            source_agent_id: None,
+            observed_at: Some(DateTime::parse_from_rfc3339("2025-01-01T00:00:00Z").unwrap().with_timezone(&Utc)),
+            received_at: DateTime::parse_from_rfc3339("2025-01-01T00:00:05Z").unwrap().with_timezone(&Utc),
+            payload_json: Some(payload),
+        }
+    }
+
+    fn det(id: u128, stage: &str, confidence: f64) -> ReplayDetection {
+        ReplayDetection {
+            raw_event_id: Uuid::from_u128(id),
+            entity_id: "e".to_string(),
+            stage: stage.to_string(),
+            confidence,
+            priority: confidence,
+            asset_criticality: None,
+        }
+    }
+
+    #[test]
+    fn event_kind_precedence_matches_normalizer() {
+        let p = serde_json::json!({"event_type": "root", "data": {"event_category": "process", "features": {"event_type": "f"}}});
+        assert_eq!(extract_event_kind(&p).as_deref(), Some("process"));
+        let p = serde_json::json!({"event_type": "root", "data": {"features": {"event_type": "f"}}});
+        assert_eq!(extract_event_kind(&p).as_deref(), Some("root"));
+        let p = serde_json::json!({"data": {"features": {"event_type": "f"}}});
+        assert_eq!(extract_event_kind(&p).as_deref(), Some("f"));
+        assert!(extract_event_kind(&serde_json::json!({"data": {}})).is_none());
+    }
+
+    #[test]
+    fn normalization_is_deterministic() {
+        let r = raw(serde_json::json!({"data": {"event_category": "process"}}));
+        let a = normalize_raw_event(&r).unwrap();
+        let b = normalize_raw_event(&r).unwrap();
+        assert_eq!(a, b);
+        assert_eq!(a.deterministic_key.len(), 32);
+        assert_eq!(a.entity_id, "unattributed:linux_agent");
+    }
+
+    #[test]
+    fn diff_classifies_added_removed_changed() {
+        let baseline = vec![det(1, "Execution", 0.7), det(2, "Impact", 0.9), det(3, "Execution", 0.8)];
+        let candidate = vec![det(1, "Execution", 0.7), det(3, "Impact", 0.8), det(4, "Discovery", 0.6)];
+        let diff = diff_detections(&baseline, &candidate);
+        assert_eq!(diff.unchanged, 1);
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.removed[0].raw_event_id, Uuid::from_u128(2));
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].candidate_stage, "Impact");
+        assert_eq!(diff.added.len(), 1);
+        assert!(!diff.is_empty());
+    }
+
+    #[test]
+    fn sandbox_schema_rejects_production_schemas() {
+        assert!(validate_sandbox_schema("ransomeye").is_err());
+        assert!(validate_sandbox_schema("public").is_err());
+        assert!(validate_sandbox_schema("x;drop").is_err());
+        assert!(validate_sandbox_schema("ransomeye_replay").is_ok());
+    }
+
+    #[test]
+    fn rule_pack_rejects_out_of_range_threshold() {
+        assert!(RulePack::parse(r#"{"version":"v2","min_confidence_threshold":1.5}"#).is_err());
+        let pack = RulePack::parse(r#"{"version":"v2","min_confidence_threshold":0.8}"#).unwrap();
+        assert_eq!(pack.engine_config().min_confidence_threshold, 0.8);
+    }
+}
@@ -0,0 +1,110 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/orchestrator/src/replay_main.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Standalone replay tool binary - streams historical raw_events through the pipeline in a sandbox schema and reports detection diffs between two rule packs.
+
+use std::path::Path;
+use std::process;
+
+use tracing::{error, info};
+
+#[path = "lib.rs"]
+mod orchestrator;
+
+use orchestrator::db::{CoreDb, DbConfig};
+use orchestrator::replay::{ReplayConfig, ReplayRunner, RulePack};
+
+fn usage_and_exit() -> ! {
+    eprintln!("RansomEye Replay Tool");
+    eprintln!("");
+    eprintln!("USAGE:");
+    eprintln!("  ransomeye_replay --baseline <rule_pack.json> --candidate <rule_pack.json>");
+    eprintln!("");
+    eprintln!("NOTES:");
+    eprintln!("  - Reads ransomeye.raw_events only; all writes go to the sandbox schema.");
+    eprintln!("  - RANSOMEYE_REPLAY_SANDBOX_SCHEMA (default: ransomeye_replay)");
+    eprintln!("  - RANSOMEYE_REPLAY_FROM / RANSOMEYE_REPLAY_TO (RFC3339, received_at window)");
+    eprintln!("  - RANSOMEYE_REPLAY_MAX_EVENTS (default: 100000)");
+    eprintln!("  - DB env vars are required: DB_HOST, DB_PORT, DB_NAME, DB_USER, DB_PASS");
+    eprintln!("  - Exit code 0 = no detection diff, 3 = detection diff found");
+    process::exit(2);
+}
+
+fn arg_value(name: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter()
+        .position(|a| a == name)
+        .and_then(|i| args.get(i + 1).cloned())
+}
+
+#[tokio::main]
+async fn main() {
+    orchestrator::BUILD_INFO.handle_version_flag("ransomeye_replay");
+    tracing_subscriber::fmt::init();
+
+    let (Some(baseline_path), Some(candidate_path)) = (arg_value("--baseline"), arg_value("--candidate")) else {
+        usage_and_exit();
+    };
+
+    let baseline = match RulePack::load(Path::new(&baseline_path)) {
+        Ok(p) => p,
+        Err(e) => {
+            error!("{e}");
+            process::exit(1);
+        }
+    };
+    let candidate = match RulePack::load(Path::new(&candidate_path)) {
+        Ok(p) => p,
+        Err(e) => {
+            error!("{e}");
+            process::exit(1);
+        }
+    };
+
+    let replay_cfg = match ReplayConfig::from_env() {
+        Ok(c) => c,
+        Err(e) => {
+            error!("{e}");
+            process::exit(1);
+        }
+    };
+
+    let cfg = match DbConfig::from_env_strict() {
+        Ok(c) => c,
+        Err(e) => {
+            error!("{e}");
+            process::exit(1);
+        }
+    };
+
+    let db = match CoreDb::connect_strict(&cfg).await {
+        Ok(db) => db,
+        Err(e) => {
+            error!("FAIL-CLOSED: DB connect failed: {e}");
+            process::exit(1);
+        }
+    };
+
+    info!(
+        "Replay starting (baseline={}, candidate={}, sandbox_schema={}, max_events={})",
+        baseline.version, candidate.version, replay_cfg.sandbox_schema, replay_cfg.max_events
+    );
+
+    let report = match ReplayRunner::new(replay_cfg).run(&db, &baseline, &candidate).await {
+        Ok(r) => r,
+        Err(e) => {
+            error!("{e}");
+            process::exit(1);
+        }
+    };
+
+    // Full report to stdout (machine-readable); summary already logged by the runner.
+    match serde_json::to_string_pretty(&report) {
+        Ok(s) => println!("{s}"),
+        Err(e) => {
+            error!("Failed to serialize replay report: {e}");
+            process::exit(1);
+        }
+    }
+
+    process::exit(if report.diff.is_empty() { 0 } else { 3 });
+}
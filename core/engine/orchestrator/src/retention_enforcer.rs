@@ -1,22 +1,29 @@
 // Path and File Name : /home/ransomeye/rebuild/core/engine/orchestrator/src/retention_enforcer.rs
 // Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
-// Details of functionality of this file: Runtime DB retention enforcer (purge-only) with fail-closed validation and immutable audit logging.
+// Details of functionality of this file: Runtime DB retention enforcer (purge-only) with fail-closed validation, legal-hold exemption and immutable audit logging.
 
 use std::collections::{HashMap, HashSet};
 
 use chrono::{DateTime, Utc};
 use serde_json::Value as JsonValue;
+use tokio_postgres::error::SqlState;
 use tokio_postgres::Row;
-use tracing::info;
+use tracing::{info, warn};
 use uuid::Uuid;
 
+use ingest::legal_hold::HoldSubject;
+use ingest::retention_runs::{self, RetentionAlertKind, RetentionRun, RetentionStatus, RunMode, RunStatus};
+use ingest::webhooks;
+
 use super::db::CoreDb;
+use super::orphan_gc::{OrphanAction, OrphanCollector, OrphanGcResult};
 
 const DENYLIST_TABLES: &[&str] = &[
     "ransomeye.immutable_audit_log",
     "ransomeye.trust_verification_records",
     "ransomeye.signature_validation_events",
     "ransomeye.retention_policies",
+    "ransomeye.legal_holds",
 ];
 
 const ALLOWED_SCHEMAS: &[&str] = &["ransomeye", "public"];
@@ -37,7 +44,14 @@ const CANDIDATE_TIME_COLUMNS: &[&str] = &[
 
 #[derive(Debug, Clone)]
 pub struct RetentionEnforcerConfig {
+    /// Starting batch size; adapted per table within [min_batch_size, max_batch_size]
     pub batch_size: i64,
+    pub min_batch_size: i64,
+    pub max_batch_size: i64,
+    /// Per-batch latency the sizer steers towards
+    pub target_batch_ms: i64,
+    /// SET LOCAL lock_timeout for each delete batch (0 = wait indefinitely)
+    pub lock_timeout_ms: i64,
     pub max_batches_per_table: i64,
     pub sleep_ms_between_batches: i64,
 }
@@ -49,6 +63,28 @@ impl RetentionEnforcerConfig {
             return Err("FAIL-CLOSED: RANSOMEYE_RETENTION_BATCH_SIZE must be > 0".to_string());
         }
 
+        let min_batch_size = env_i64("RANSOMEYE_RETENTION_MIN_BATCH_SIZE", 100)?;
+        if min_batch_size <= 0 {
+            return Err("FAIL-CLOSED: RANSOMEYE_RETENTION_MIN_BATCH_SIZE must be > 0".to_string());
+        }
+
+        let max_batch_size = env_i64("RANSOMEYE_RETENTION_MAX_BATCH_SIZE", 10_000)?;
+        if !(min_batch_size..=max_batch_size).contains(&batch_size) {
+            return Err(format!(
+                "FAIL-CLOSED: retention batch sizes must satisfy RANSOMEYE_RETENTION_MIN_BATCH_SIZE ({min_batch_size}) <= RANSOMEYE_RETENTION_BATCH_SIZE ({batch_size}) <= RANSOMEYE_RETENTION_MAX_BATCH_SIZE ({max_batch_size})"
+            ));
+        }
+
+        let target_batch_ms = env_i64("RANSOMEYE_RETENTION_TARGET_BATCH_MS", 500)?;
+        if target_batch_ms <= 0 {
+            return Err("FAIL-CLOSED: RANSOMEYE_RETENTION_TARGET_BATCH_MS must be > 0".to_string());
+        }
+
+        let lock_timeout_ms = env_i64("RANSOMEYE_RETENTION_LOCK_TIMEOUT_MS", 2000)?;
+        if lock_timeout_ms < 0 {
+            return Err("FAIL-CLOSED: RANSOMEYE_RETENTION_LOCK_TIMEOUT_MS must be >= 0".to_string());
+        }
+
         let max_batches_per_table = env_i64("RANSOMEYE_RETENTION_MAX_BATCHES_PER_TABLE", 200)?;
         if max_batches_per_table <= 0 {
             return Err("FAIL-CLOSED: RANSOMEYE_RETENTION_MAX_BATCHES_PER_TABLE must be > 0".to_string());
@@ -61,12 +97,102 @@ impl RetentionEnforcerConfig {
 
         Ok(Self {
             batch_size,
+            min_batch_size,
+            max_batch_size,
+            target_batch_ms,
+            lock_timeout_ms,
             max_batches_per_table,
             sleep_ms_between_batches,
         })
     }
 }
 
+/// Measurements of one delete batch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchObservation {
+    pub batch_size: i64,
+    pub deleted: i64,
+    pub latency_ms: i64,
+    /// Sessions blocked behind the batch's locks, sampled just before commit
+    pub lock_waiters: i64,
+    /// The batch gave up waiting for a lock (lock_timeout) and was rolled back
+    pub lock_timed_out: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchAdjustment {
+    Hold,
+    Grow,
+    Shrink,
+    /// Halved because the batch waited on, or made others wait on, a lock
+    LockBackoff,
+}
+
+impl BatchAdjustment {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BatchAdjustment::Hold => "hold",
+            BatchAdjustment::Grow => "grow",
+            BatchAdjustment::Shrink => "shrink",
+            BatchAdjustment::LockBackoff => "lock_backoff",
+        }
+    }
+}
+
+/// Per-table batch size controller. Scales the batch towards the target latency (at most 2x up or
+/// 4x down per step, growing only after a full batch) and halves it on lock contention.
+#[derive(Debug, Clone)]
+pub struct BatchSizer {
+    min: i64,
+    max: i64,
+    target_ms: i64,
+    current: i64,
+}
+
+impl BatchSizer {
+    pub fn new(cfg: &RetentionEnforcerConfig) -> Self {
+        Self {
+            min: cfg.min_batch_size,
+            max: cfg.max_batch_size,
+            target_ms: cfg.target_batch_ms,
+            current: cfg.batch_size.clamp(cfg.min_batch_size, cfg.max_batch_size),
+        }
+    }
+
+    pub fn current(&self) -> i64 {
+        self.current
+    }
+
+    pub fn observe(&mut self, obs: &BatchObservation) -> BatchAdjustment {
+        let latency_ms = obs.latency_ms.max(1);
+        let (proposed, adjustment) = if obs.lock_timed_out || obs.lock_waiters > 0 {
+            (self.current / 2, BatchAdjustment::LockBackoff)
+        } else if latency_ms * 4 > self.target_ms * 5 {
+            ((self.current * self.target_ms / latency_ms).max(self.current / 4), BatchAdjustment::Shrink)
+        } else if latency_ms * 4 < self.target_ms * 3 && obs.deleted >= obs.batch_size {
+            ((self.current * self.target_ms / latency_ms).min(self.current * 2), BatchAdjustment::Grow)
+        } else {
+            (self.current, BatchAdjustment::Hold)
+        };
+
+        let next = proposed.clamp(self.min, self.max);
+        if next == self.current {
+            return BatchAdjustment::Hold;
+        }
+        self.current = next;
+        adjustment
+    }
+}
+
+/// One entry of a table's adaptation trace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchTraceEntry {
+    pub batch: i64,
+    pub observation: BatchObservation,
+    pub adjustment: BatchAdjustment,
+    pub next_batch_size: i64,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct QualifiedTable {
     pub schema: String,
@@ -126,6 +252,75 @@ impl QualifiedTable {
     }
 }
 
+/// Foreign key whose parent is a retention target (`child` may or may not be targeted itself).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FkDependency {
+    pub constraint: String,
+    pub child: QualifiedTable,
+    pub child_columns: Vec<String>,
+    pub parent: QualifiedTable,
+    pub parent_columns: Vec<String>,
+    /// pg_constraint.confdeltype: a (no action), r (restrict), c (cascade), n (set null), d (set default)
+    pub on_delete: String,
+    pub deferrable: bool,
+}
+
+impl FkDependency {
+    pub fn on_delete_label(&self) -> &'static str {
+        match self.on_delete.as_str() {
+            "a" => "no_action",
+            "r" => "restrict",
+            "c" => "cascade",
+            "n" => "set_null",
+            "d" => "set_default",
+            _ => "unknown",
+        }
+    }
+}
+
+/// Order in which targets are purged: referencing (child) tables before the tables they reference.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeletionPlan {
+    pub order: Vec<(QualifiedTable, i64)>,
+    /// Targets on (or waiting behind) a reference cycle; purged in policy order after the others
+    pub cyclic: Vec<QualifiedTable>,
+}
+
+/// Children-first order over `policies` (Kahn's algorithm; ties keep policy order). Self-references
+/// do not constrain the order.
+pub fn plan_deletion_order(policies: &[(QualifiedTable, i64)], deps: &[FkDependency]) -> DeletionPlan {
+    let targets: Vec<&QualifiedTable> = policies.iter().map(|(qt, _)| qt).collect();
+    // waiting_on[i] = targeted children of target i not yet purged
+    let mut waiting_on: Vec<usize> = vec![0; policies.len()];
+    let mut edges: Vec<(usize, usize)> = Vec::new();
+    for dep in deps {
+        let child = targets.iter().position(|t| **t == dep.child);
+        let parent = targets.iter().position(|t| **t == dep.parent);
+        if let (Some(c), Some(p)) = (child, parent) {
+            if c != p && !edges.contains(&(c, p)) {
+                edges.push((c, p));
+                waiting_on[p] += 1;
+            }
+        }
+    }
+
+    let mut done = vec![false; policies.len()];
+    let mut order = Vec::with_capacity(policies.len());
+    while let Some(next) = (0..policies.len()).find(|&i| !done[i] && waiting_on[i] == 0) {
+        done[next] = true;
+        order.push(policies[next].clone());
+        for &(c, p) in &edges {
+            if c == next {
+                waiting_on[p] -= 1;
+            }
+        }
+    }
+
+    let cyclic: Vec<QualifiedTable> = (0..policies.len()).filter(|&i| !done[i]).map(|i| policies[i].0.clone()).collect();
+    order.extend((0..policies.len()).filter(|&i| !done[i]).map(|i| policies[i].clone()));
+    DeletionPlan { order, cyclic }
+}
+
 #[derive(Debug, Clone)]
 pub struct TableRetentionResult {
     pub table: QualifiedTable,
@@ -137,6 +332,20 @@ pub struct TableRetentionResult {
     pub dry_run_rows_older: Option<i64>,
     pub deleted_rows: i64,
     pub batches_executed: i64,
+    /// uuid columns linking rows to legal-hold subjects (incident_id, *entity_id)
+    pub hold_columns: Vec<String>,
+    /// Rows past the cutoff kept because they are linked to an active legal hold
+    pub held_rows_skipped: i64,
+    /// Position in the children-first deletion order (0 = purged first)
+    pub delete_order: usize,
+    /// Foreign keys referencing this table; rows still referenced through them are kept
+    pub referenced_by: Vec<FkDependency>,
+    /// Rows past the cutoff kept because another row still references them
+    pub referenced_rows_skipped: i64,
+    /// Batches rolled back after hitting lock_timeout
+    pub lock_timeouts: i64,
+    /// Every live batch with its measurements and the resulting size change
+    pub batch_trace: Vec<BatchTraceEntry>,
 }
 
 pub struct RetentionEnforcer {
@@ -152,6 +361,9 @@ impl RetentionEnforcer {
         Ok(Self::new(RetentionEnforcerConfig::from_env()?))
     }
 
+    /// One retention run, recorded in retention_runs whether it succeeds or fails. A failed live
+    /// run also raises a retention.alert webhook.
+    #[tracing::instrument(name = "retention.enforce", skip_all, fields(dry_run = dry_run))]
     pub async fn enforce(
         &self,
         db: &CoreDb,
@@ -160,7 +372,48 @@ impl RetentionEnforcer {
     ) -> Result<(Uuid, Vec<TableRetentionResult>), String> {
         let run_id = Uuid::new_v4();
         let started_at = Utc::now();
+        let mode = if dry_run { RunMode::DryRun } else { RunMode::Live };
+
+        match self.run(db, actor_component_id, dry_run, run_id, started_at).await {
+            Ok((audit_id, results, orphans)) => {
+                let run = build_run_record(run_id, mode, started_at, Utc::now(), audit_id, &results, &orphans);
+                // Fail-closed: a run missing from the history would read as a skipped schedule
+                retention_runs::record(db.client(), &run).await.map_err(|e| format!("FAIL-CLOSED: {}", e))?;
+                Ok((audit_id, results))
+            }
+            Err(e) => {
+                let run = RetentionRun::failed(run_id, mode, started_at, Utc::now(), &e);
+                if let Err(record_err) = retention_runs::record(db.client(), &run).await {
+                    warn!("{}", record_err);
+                }
+                if !dry_run {
+                    let alert = webhooks::WebhookEvent::new(
+                        webhooks::RETENTION_ALERT,
+                        serde_json::json!({
+                            "kind": RetentionAlertKind::Failed.as_str(),
+                            "message": format!("live run failed: {}", e),
+                            "run_id": run_id.to_string(),
+                            "mode": mode.as_str(),
+                            "error": run.error,
+                        }),
+                    );
+                    if let Err(enqueue_err) = webhooks::enqueue(db.client(), &alert).await {
+                        warn!("Failed to enqueue {} webhook for run {}: {}", webhooks::RETENTION_ALERT, run_id, enqueue_err);
+                    }
+                }
+                Err(e)
+            }
+        }
+    }
 
+    async fn run(
+        &self,
+        db: &CoreDb,
+        actor_component_id: Option<Uuid>,
+        dry_run: bool,
+        run_id: Uuid,
+        started_at: DateTime<Utc>,
+    ) -> Result<(Uuid, Vec<TableRetentionResult>, Vec<OrphanGcResult>), String> {
         // Fail-closed: retention_policies MUST exist and MUST have enabled rows.
         let policies = self.fetch_enabled_policies(db).await?;
         if policies.is_empty() {
@@ -188,14 +441,45 @@ impl RetentionEnforcer {
             }
         }
 
+        // Fail-closed: an unreadable hold list must never be treated as "no holds".
+        let active_holds = self.count_active_legal_holds(db).await?;
+
+        // Children first, and never delete a row something still references: a purge cannot
+        // stop halfway on a foreign key violation.
+        let deps = self.fetch_fk_dependencies(db, &policies).await?;
+        let plan = plan_deletion_order(&policies, &deps);
+        if !plan.cyclic.is_empty() {
+            warn!(
+                "[RETENTION] Reference cycle among retention targets: {} (rows on the cycle are kept while referenced)",
+                plan.cyclic.iter().map(QualifiedTable::as_fqn).collect::<Vec<_>>().join(", ")
+            );
+        }
+
         let mut results: Vec<TableRetentionResult> = Vec::new();
-        for (qt, retention_days) in policies {
-            let res = self.enforce_one_table(db, &append_only, &qt, retention_days, dry_run).await?;
+        for (position, (qt, retention_days)) in plan.order.iter().enumerate() {
+            let referenced_by: Vec<FkDependency> = deps.iter().filter(|d| d.parent == *qt).cloned().collect();
+            let mut res = self
+                .enforce_one_table(db, &append_only, qt, *retention_days, &referenced_by, dry_run)
+                .await?;
+            res.delete_order = position;
             results.push(res);
         }
 
+        // Derived rows left pointing at purged sources (soft links the FK guard cannot see)
+        let orphans = OrphanCollector::new(self.cfg.clone()).collect(db, dry_run).await?;
+
         let ended_at = Utc::now();
-        let payload = build_audit_payload(run_id, started_at, ended_at, dry_run, &self.cfg, &results);
+        let payload = build_audit_payload(
+            run_id,
+            started_at,
+            ended_at,
+            dry_run,
+            &self.cfg,
+            active_holds,
+            &plan,
+            &results,
+            &orphans,
+        );
         let audit_id = db
             .insert_immutable_audit_log(
                 actor_component_id,
@@ -210,7 +494,18 @@ impl RetentionEnforcer {
             )
             .await?;
 
-        Ok((audit_id, results))
+        // Notify subscribers of real runs only; the deletions are already committed, so a failed
+        // enqueue is logged rather than failing the run
+        if !dry_run {
+            let mut data = payload;
+            data["audit_id"] = JsonValue::String(audit_id.to_string());
+            let event = webhooks::WebhookEvent::new(webhooks::RETENTION_RUN, data);
+            if let Err(e) = webhooks::enqueue(db.client(), &event).await {
+                warn!("Failed to enqueue {} webhook for run {}: {}", webhooks::RETENTION_RUN, run_id, e);
+            }
+        }
+
+        Ok((audit_id, results, orphans))
     }
 
     async fn fetch_enabled_policies(&self, db: &CoreDb) -> Result<Vec<(QualifiedTable, i64)>, String> {
@@ -264,6 +559,85 @@ impl RetentionEnforcer {
         Ok(out)
     }
 
+    async fn count_active_legal_holds(&self, db: &CoreDb) -> Result<i64, String> {
+        let row = db
+            .client()
+            .query_one(
+                "SELECT COUNT(*)::bigint FROM ransomeye.legal_holds WHERE released_at IS NULL",
+                &[],
+            )
+            .await
+            .map_err(|e| format!("FAIL-CLOSED: Cannot read ransomeye.legal_holds: {e}"))?;
+        Ok(row.get(0))
+    }
+
+    /// Foreign keys referencing any retention target, from pg_constraint (columns in key order).
+    async fn fetch_fk_dependencies(
+        &self,
+        db: &CoreDb,
+        policies: &[(QualifiedTable, i64)],
+    ) -> Result<Vec<FkDependency>, String> {
+        let targets: Vec<String> = policies.iter().map(|(qt, _)| qt.as_fqn()).collect();
+        let rows = db
+            .client()
+            .query(
+                r#"
+                SELECT con.conname::text,
+                       cn.nspname::text, cc.relname::text,
+                       pn.nspname::text, pc.relname::text,
+                       con.confdeltype::text,
+                       con.condeferrable,
+                       ARRAY(SELECT a.attname::text
+                             FROM unnest(con.conkey) WITH ORDINALITY AS k(attnum, ord)
+                             JOIN pg_attribute a ON a.attrelid = con.conrelid AND a.attnum = k.attnum
+                             ORDER BY k.ord),
+                       ARRAY(SELECT a.attname::text
+                             FROM unnest(con.confkey) WITH ORDINALITY AS k(attnum, ord)
+                             JOIN pg_attribute a ON a.attrelid = con.confrelid AND a.attnum = k.attnum
+                             ORDER BY k.ord)
+                FROM pg_constraint con
+                JOIN pg_class cc ON cc.oid = con.conrelid
+                JOIN pg_namespace cn ON cn.oid = cc.relnamespace
+                JOIN pg_class pc ON pc.oid = con.confrelid
+                JOIN pg_namespace pn ON pn.oid = pc.relnamespace
+                WHERE con.contype = 'f'
+                  AND (pn.nspname || '.' || pc.relname) = ANY($1)
+                ORDER BY con.conname
+                "#,
+                &[&targets],
+            )
+            .await
+            .map_err(|e| format!("FAIL-CLOSED: Cannot discover foreign keys referencing retention targets: {e}"))?;
+
+        let mut deps = Vec::with_capacity(rows.len());
+        for r in rows {
+            let dep = FkDependency {
+                constraint: r.get(0),
+                child: QualifiedTable { schema: r.get(1), table: r.get(2) },
+                parent: QualifiedTable { schema: r.get(3), table: r.get(4) },
+                on_delete: r.get(5),
+                deferrable: r.get(6),
+                child_columns: r.get(7),
+                parent_columns: r.get(8),
+            };
+            // Fail-closed: these names are interpolated into the purge predicate
+            QualifiedTable::quote_ident(&dep.child.schema)?;
+            QualifiedTable::quote_ident(&dep.child.table)?;
+            if dep.child_columns.is_empty() || dep.child_columns.len() != dep.parent_columns.len() {
+                return Err(format!("FAIL-CLOSED: Malformed foreign key '{}' on {}", dep.constraint, dep.child.as_fqn()));
+            }
+            info!(
+                "[RETENTION] {} referenced by {} via {} (on delete {})",
+                dep.parent.as_fqn(),
+                dep.child.as_fqn(),
+                dep.constraint,
+                dep.on_delete_label()
+            );
+            deps.push(dep);
+        }
+        Ok(deps)
+    }
+
     async fn fetch_append_only_tables(&self, db: &CoreDb) -> Result<HashSet<String>, String> {
         let rows = db
             .client()
@@ -291,12 +665,14 @@ impl RetentionEnforcer {
         Ok(set)
     }
 
+    #[tracing::instrument(name = "retention.table", skip_all, fields(table = %qt.as_fqn(), retention_days = retention_days))]
     async fn enforce_one_table(
         &self,
         db: &CoreDb,
         append_only: &HashSet<String>,
         qt: &QualifiedTable,
         retention_days: i64,
+        referenced_by: &[FkDependency],
         dry_run: bool,
     ) -> Result<TableRetentionResult, String> {
         let started = Utc::now();
@@ -317,6 +693,9 @@ impl RetentionEnforcer {
 
         // Determine time column used for retention cutoff.
         let time_col = self.find_time_column(db, qt).await?;
+        let hold_cols = hold_columns(db, qt).await?;
+        let held = held_row_predicate("r", &hold_cols)?;
+        let referenced = referenced_row_predicate("r", referenced_by)?;
 
         // Compute cutoff timestamp deterministically from NOW() in DB, but also provide a local approximation for reporting.
         let cutoff = Utc::now() - chrono::Duration::days(retention_days);
@@ -331,11 +710,36 @@ impl RetentionEnforcer {
             dry_run_rows_older: None,
             deleted_rows: 0,
             batches_executed: 0,
+            hold_columns: hold_cols.iter().map(|(c, _)| c.clone()).collect(),
+            held_rows_skipped: 0,
+            delete_order: 0,
+            referenced_by: referenced_by.to_vec(),
+            referenced_rows_skipped: 0,
+            lock_timeouts: 0,
+            batch_trace: Vec::new(),
         };
 
         // Dry-run: counts only (no deletes).
-        let rows_older = self.count_rows_older_than_cutoff(db, qt, &time_col, retention_days).await?;
+        let (rows_older, held_rows, referenced_rows) = self
+            .count_rows_older_than_cutoff(db, qt, &time_col, retention_days, &held, &referenced)
+            .await?;
         result.dry_run_rows_older = Some(rows_older);
+        result.held_rows_skipped = held_rows;
+        result.referenced_rows_skipped = referenced_rows;
+        if held_rows > 0 {
+            info!(
+                "[RETENTION] {} row(s) past retention in {} kept under legal hold",
+                held_rows,
+                qt.as_fqn()
+            );
+        }
+        if referenced_rows > 0 {
+            info!(
+                "[RETENTION] {} row(s) past retention in {} kept while still referenced",
+                referenced_rows,
+                qt.as_fqn()
+            );
+        }
 
         if dry_run {
             info!(
@@ -361,14 +765,32 @@ impl RetentionEnforcer {
 
         let mut total_deleted: i64 = 0;
         let mut batches: i64 = 0;
+        let mut sizer = BatchSizer::new(&self.cfg);
         for _ in 0..self.cfg.max_batches_per_table {
-            let deleted = self
-                .delete_batch(db, qt, &time_col, retention_days, self.cfg.batch_size)
+            let obs = self
+                .delete_batch(db, qt, &time_col, retention_days, sizer.current(), &held, &referenced)
                 .await?;
             batches += 1;
-            total_deleted += deleted;
+            total_deleted += obs.deleted;
+            if obs.lock_timed_out {
+                result.lock_timeouts += 1;
+                warn!(
+                    "[RETENTION] Batch {} on {} hit lock_timeout ({} ms); rolled back",
+                    batches,
+                    qt.as_fqn(),
+                    self.cfg.lock_timeout_ms
+                );
+            }
+
+            let adjustment = sizer.observe(&obs);
+            result.batch_trace.push(BatchTraceEntry {
+                batch: batches,
+                observation: obs,
+                adjustment,
+                next_batch_size: sizer.current(),
+            });
 
-            if deleted == 0 {
+            if obs.deleted == 0 && !obs.lock_timed_out {
                 break;
             }
 
@@ -385,11 +807,13 @@ impl RetentionEnforcer {
 
         let elapsed_ms = (Utc::now() - started).num_milliseconds();
         info!(
-            "[RETENTION] Purged {} row(s) from {} in {} batch(es) ({} ms)",
+            "[RETENTION] Purged {} row(s) from {} in {} batch(es) ({} ms, final batch_size={}, lock_timeouts={})",
             total_deleted,
             qt.as_fqn(),
             batches,
-            elapsed_ms
+            elapsed_ms,
+            sizer.current(),
+            result.lock_timeouts
         );
 
         Ok(result)
@@ -454,22 +878,35 @@ impl RetentionEnforcer {
         ))
     }
 
+    /// (purgeable rows, rows past the cutoff kept under legal hold, rows past the cutoff kept
+    /// because they are still referenced)
+    #[allow(clippy::too_many_arguments)]
     async fn count_rows_older_than_cutoff(
         &self,
         db: &CoreDb,
         qt: &QualifiedTable,
         time_col: &str,
         retention_days: i64,
-    ) -> Result<i64, String> {
+        held: &str,
+        referenced: &str,
+    ) -> Result<(i64, i64, i64), String> {
         let schema_q = QualifiedTable::quote_ident(&qt.schema)?;
         let table_q = QualifiedTable::quote_ident(&qt.table)?;
         let col_q = QualifiedTable::quote_ident(time_col)?;
 
         let sql = format!(
-            "SELECT COUNT(*)::bigint FROM {schema}.{table} WHERE {col} < (NOW() - ($1::int * INTERVAL '1 day'))",
+            r#"
+            SELECT COUNT(*) FILTER (WHERE NOT {held} AND NOT {referenced})::bigint,
+                   COUNT(*) FILTER (WHERE {held})::bigint,
+                   COUNT(*) FILTER (WHERE NOT {held} AND {referenced})::bigint
+            FROM {schema}.{table} r
+            WHERE r.{col} < (NOW() - ($1::int * INTERVAL '1 day'))
+            "#,
             schema = schema_q,
             table = table_q,
-            col = col_q
+            col = col_q,
+            held = held,
+            referenced = referenced
         );
 
         let row = db
@@ -477,9 +914,14 @@ impl RetentionEnforcer {
             .query_one(&sql, &[&(retention_days as i32)])
             .await
             .map_err(|e| format!("FAIL-CLOSED: Count query failed for {}: {e}", qt.as_fqn()))?;
-        Ok(row.get::<usize, i64>(0))
+        Ok((row.get::<usize, i64>(0), row.get::<usize, i64>(1), row.get::<usize, i64>(2)))
     }
 
+    /// One delete batch in its own transaction under `lock_timeout`. Sessions blocked behind the
+    /// batch are sampled before commit; a lock timeout rolls the batch back and is reported, not
+    /// raised.
+    #[allow(clippy::too_many_arguments)]
+    #[tracing::instrument(name = "retention.delete_batch", skip_all, fields(table = %qt.as_fqn(), batch_size = batch_size))]
     async fn delete_batch(
         &self,
         db: &CoreDb,
@@ -487,7 +929,9 @@ impl RetentionEnforcer {
         time_col: &str,
         retention_days: i64,
         batch_size: i64,
-    ) -> Result<i64, String> {
+        held: &str,
+        referenced: &str,
+    ) -> Result<BatchObservation, String> {
         let schema_q = QualifiedTable::quote_ident(&qt.schema)?;
         let table_q = QualifiedTable::quote_ident(&qt.table)?;
         let col_q = QualifiedTable::quote_ident(time_col)?;
@@ -495,10 +939,12 @@ impl RetentionEnforcer {
         let sql = format!(
             r#"
             WITH todel AS (
-                SELECT ctid
-                FROM {schema}.{table}
-                WHERE {col} < (NOW() - ($1::int * INTERVAL '1 day'))
-                ORDER BY {col} ASC
+                SELECT r.ctid
+                FROM {schema}.{table} r
+                WHERE r.{col} < (NOW() - ($1::int * INTERVAL '1 day'))
+                  AND NOT {held}
+                  AND NOT {referenced}
+                ORDER BY r.{col} ASC
                 LIMIT $2
             )
             DELETE FROM {schema}.{table} t
@@ -508,15 +954,59 @@ impl RetentionEnforcer {
             "#,
             schema = schema_q,
             table = table_q,
-            col = col_q
+            col = col_q,
+            held = held,
+            referenced = referenced
         );
 
-        let rows = db
-            .client()
-            .query(&sql, &[&(retention_days as i32), &(batch_size as i64)])
+        let started = std::time::Instant::now();
+        db.client()
+            .batch_execute(&format!("BEGIN; SET LOCAL lock_timeout = {}", self.cfg.lock_timeout_ms))
             .await
-            .map_err(|e| format!("FAIL-CLOSED: Delete batch failed for {}: {e}", qt.as_fqn()))?;
-        Ok(rows.len() as i64)
+            .map_err(|e| format!("FAIL-CLOSED: Cannot begin delete batch for {}: {e}", qt.as_fqn()))?;
+
+        let batch = async {
+            let rows = db.client().query(&sql, &[&(retention_days as i32), &batch_size]).await?;
+            let waiters = db
+                .client()
+                .query_one(
+                    "SELECT COUNT(*)::bigint FROM pg_stat_activity \
+                     WHERE wait_event_type = 'Lock' AND pg_backend_pid() = ANY(pg_blocking_pids(pid))",
+                    &[],
+                )
+                .await?;
+            Ok::<_, tokio_postgres::Error>((rows.len() as i64, waiters.get::<usize, i64>(0)))
+        }
+        .await;
+
+        match batch {
+            Ok((deleted, lock_waiters)) => {
+                db.client()
+                    .batch_execute("COMMIT")
+                    .await
+                    .map_err(|e| format!("FAIL-CLOSED: Delete batch commit failed for {}: {e}", qt.as_fqn()))?;
+                Ok(BatchObservation {
+                    batch_size,
+                    deleted,
+                    latency_ms: started.elapsed().as_millis() as i64,
+                    lock_waiters,
+                    lock_timed_out: false,
+                })
+            }
+            Err(e) => {
+                let _ = db.client().batch_execute("ROLLBACK").await;
+                if e.code() == Some(&SqlState::LOCK_NOT_AVAILABLE) {
+                    return Ok(BatchObservation {
+                        batch_size,
+                        deleted: 0,
+                        latency_ms: started.elapsed().as_millis() as i64,
+                        lock_waiters: 0,
+                        lock_timed_out: true,
+                    });
+                }
+                Err(format!("FAIL-CLOSED: Delete batch failed for {}: {e}", qt.as_fqn()))
+            }
+        }
     }
 }
 
@@ -529,13 +1019,94 @@ fn env_i64(key: &str, default_value: i64) -> Result<i64, String> {
     }
 }
 
+/// uuid columns of `qt` that link its rows to legal-hold subjects.
+pub(crate) async fn hold_columns(db: &CoreDb, qt: &QualifiedTable) -> Result<Vec<(String, HoldSubject)>, String> {
+    let rows: Vec<Row> = db
+        .client()
+        .query(
+            r#"
+            SELECT column_name
+            FROM information_schema.columns
+            WHERE table_schema = $1 AND table_name = $2 AND data_type = 'uuid'
+            ORDER BY column_name
+            "#,
+            &[&qt.schema, &qt.table],
+        )
+        .await
+        .map_err(|e| format!("FAIL-CLOSED: Cannot read uuid columns for {}: {e}", qt.as_fqn()))?;
+
+    Ok(rows
+        .iter()
+        .filter_map(|r| {
+            let col: String = r.get(0);
+            HoldSubject::linked_by_column(&col).map(|s| (col, s))
+        })
+        .collect())
+}
+
+/// SQL boolean: row `alias` is linked to an active legal hold through one of `hold_cols`.
+/// Constant FALSE when the table has no linking column.
+pub(crate) fn held_row_predicate(alias: &str, hold_cols: &[(String, HoldSubject)]) -> Result<String, String> {
+    if hold_cols.is_empty() {
+        return Ok("FALSE".to_string());
+    }
+    let alias_q = QualifiedTable::quote_ident(alias)?;
+    let mut links: Vec<String> = Vec::new();
+    for (col, subject) in hold_cols {
+        links.push(format!(
+            "(h.subject_type = '{}' AND h.subject_id = {}.{})",
+            subject.as_str(),
+            alias_q,
+            QualifiedTable::quote_ident(col)?
+        ));
+    }
+    Ok(format!(
+        "EXISTS (SELECT 1 FROM ransomeye.legal_holds h WHERE h.released_at IS NULL AND ({}))",
+        links.join(" OR ")
+    ))
+}
+
+/// SQL boolean: row `alias` is still referenced through one of `deps` (all of whose parent is the
+/// table `alias` ranges over). Constant FALSE when nothing references the table. Applies to every
+/// ON DELETE action: cascades and SET NULL/DEFAULT would otherwise change rows outside this
+/// table's policy.
+fn referenced_row_predicate(alias: &str, deps: &[FkDependency]) -> Result<String, String> {
+    if deps.is_empty() {
+        return Ok("FALSE".to_string());
+    }
+    let alias_q = QualifiedTable::quote_ident(alias)?;
+    let mut refs: Vec<String> = Vec::new();
+    for dep in deps {
+        let mut join: Vec<String> = Vec::new();
+        for (child_col, parent_col) in dep.child_columns.iter().zip(&dep.parent_columns) {
+            join.push(format!(
+                "c.{} = {}.{}",
+                QualifiedTable::quote_ident(child_col)?,
+                alias_q,
+                QualifiedTable::quote_ident(parent_col)?
+            ));
+        }
+        refs.push(format!(
+            "EXISTS (SELECT 1 FROM {}.{} c WHERE {})",
+            QualifiedTable::quote_ident(&dep.child.schema)?,
+            QualifiedTable::quote_ident(&dep.child.table)?,
+            join.join(" AND ")
+        ));
+    }
+    Ok(format!("({})", refs.join(" OR ")))
+}
+
+#[allow(clippy::too_many_arguments)]
 fn build_audit_payload(
     run_id: Uuid,
     started_at: DateTime<Utc>,
     ended_at: DateTime<Utc>,
     dry_run: bool,
     cfg: &RetentionEnforcerConfig,
+    active_holds: i64,
+    plan: &DeletionPlan,
     results: &[TableRetentionResult],
+    orphans: &[OrphanGcResult],
 ) -> JsonValue {
     let mut per_table: Vec<JsonValue> = Vec::new();
     for r in results {
@@ -548,7 +1119,31 @@ fn build_audit_payload(
             "cutoff_utc": r.cutoff.to_rfc3339(),
             "dry_run_rows_older": r.dry_run_rows_older,
             "deleted_rows": r.deleted_rows,
-            "batches_executed": r.batches_executed
+            "batches_executed": r.batches_executed,
+            "legal_hold_columns": r.hold_columns,
+            "held_rows_skipped": r.held_rows_skipped,
+            "delete_order": r.delete_order,
+            "referenced_by": r.referenced_by.iter().map(|d| serde_json::json!({
+                "constraint": d.constraint,
+                "child": d.child.as_fqn(),
+                "child_columns": d.child_columns,
+                "parent_columns": d.parent_columns,
+                "on_delete": d.on_delete_label(),
+                "deferrable": d.deferrable,
+                "child_targeted": plan.order.iter().any(|(qt, _)| *qt == d.child)
+            })).collect::<Vec<_>>(),
+            "referenced_rows_skipped": r.referenced_rows_skipped,
+            "lock_timeouts": r.lock_timeouts,
+            "batch_trace": r.batch_trace.iter().map(|t| serde_json::json!({
+                "batch": t.batch,
+                "batch_size": t.observation.batch_size,
+                "deleted": t.observation.deleted,
+                "latency_ms": t.observation.latency_ms,
+                "lock_waiters": t.observation.lock_waiters,
+                "lock_timed_out": t.observation.lock_timed_out,
+                "adjustment": t.adjustment.as_str(),
+                "next_batch_size": t.next_batch_size
+            })).collect::<Vec<_>>()
         }));
     }
 
@@ -560,18 +1155,185 @@ fn build_audit_payload(
         "ended_at_utc": ended_at.to_rfc3339(),
         "config": {
             "batch_size": cfg.batch_size,
+            "min_batch_size": cfg.min_batch_size,
+            "max_batch_size": cfg.max_batch_size,
+            "target_batch_ms": cfg.target_batch_ms,
+            "lock_timeout_ms": cfg.lock_timeout_ms,
             "max_batches_per_table": cfg.max_batches_per_table,
             "sleep_ms_between_batches": cfg.sleep_ms_between_batches
         },
         "protected_tables_denylist": DENYLIST_TABLES,
         "append_only_trigger_function": "prevent_update_delete",
+        "legal_hold": {
+            "active_holds": active_holds,
+            "held_rows_skipped": results.iter().map(|r| r.held_rows_skipped).sum::<i64>()
+        },
+        "foreign_keys": {
+            "strategy": "children_first_skip_referenced",
+            "delete_order": plan.order.iter().map(|(qt, _)| qt.as_fqn()).collect::<Vec<_>>(),
+            "cyclic_tables": plan.cyclic.iter().map(QualifiedTable::as_fqn).collect::<Vec<_>>(),
+            "referenced_rows_skipped": results.iter().map(|r| r.referenced_rows_skipped).sum::<i64>()
+        },
+        "orphan_gc": {
+            "links": orphans.iter().map(|o| serde_json::json!({
+                "link": o.link,
+                "derived": o.derived.as_fqn(),
+                "source": o.source.as_fqn(),
+                "action": o.action.as_str(),
+                "skipped_reason": o.skipped_reason,
+                "orphans_found": o.orphans_found,
+                "held_rows_skipped": o.held_rows_skipped,
+                "rows_deleted": o.rows_deleted,
+                "rows_marked": o.rows_marked,
+                "batches_executed": o.batches_executed
+            })).collect::<Vec<_>>(),
+            "rows_deleted": orphans.iter().filter(|o| o.action == OrphanAction::Delete).map(|o| o.rows_deleted).sum::<i64>(),
+            "rows_marked": orphans.iter().map(|o| o.rows_marked).sum::<i64>()
+        },
         "results": per_table
     })
 }
 
+/// Audit action recording that a schedule alert was sent
+const SCHEDULE_ALERT_ACTION: &str = "retention_schedule_alert";
+
+/// Enqueues a retention.alert webhook for each overdue or never-run alert of `status` that was not
+/// sent before; returns how many were sent. Failed runs are alerted by the run itself. An alert is
+/// sent once per last successful run (once in all for never-run), remembered in the audit log.
+pub async fn notify_schedule_alerts(
+    db: &CoreDb,
+    actor_component_id: Option<Uuid>,
+    status: &RetentionStatus,
+) -> Result<usize, String> {
+    let mut sent = 0;
+    for alert in status.alerts.iter().filter(|a| a.kind != RetentionAlertKind::Failed) {
+        let run_id = alert.run_id.map(|id| id.to_string());
+        let already_sent: bool = db
+            .client()
+            .query_one(
+                r#"
+                SELECT EXISTS (
+                    SELECT 1 FROM immutable_audit_log
+                    WHERE action = $1 AND payload_json->>'kind' = $2
+                      AND payload_json->>'run_id' IS NOT DISTINCT FROM $3
+                )
+                "#,
+                &[&SCHEDULE_ALERT_ACTION, &alert.kind.as_str(), &run_id],
+            )
+            .await
+            .map_err(|e| format!("Failed to read sent retention alerts: {e}"))?
+            .get(0);
+        if already_sent {
+            continue;
+        }
+        let data = serde_json::json!({
+            "kind": alert.kind.as_str(),
+            "message": alert.message,
+            "run_id": run_id,
+            "max_age_hours": status.max_age_hours
+        });
+        // Enqueue before remembering: a crash in between repeats the alert rather than losing it
+        webhooks::enqueue(db.client(), &webhooks::WebhookEvent::new(webhooks::RETENTION_ALERT, data.clone()))
+            .await
+            .map_err(|e| format!("Failed to enqueue {} webhook: {e}", webhooks::RETENTION_ALERT))?;
+        db.insert_immutable_audit_log(actor_component_id, SCHEDULE_ALERT_ACTION, "other", actor_component_id, &data)
+            .await?;
+        warn!("[RETENTION] Alert sent: {}", alert.message);
+        sent += 1;
+    }
+    Ok(sent)
+}
+
+/// retention_runs row of a successful run: totals over the targets and orphan links, and a per
+/// table summary without the batch traces (those stay in the audit payload).
+fn build_run_record(
+    run_id: Uuid,
+    mode: RunMode,
+    started_at: DateTime<Utc>,
+    ended_at: DateTime<Utc>,
+    audit_id: Uuid,
+    results: &[TableRetentionResult],
+    orphans: &[OrphanGcResult],
+) -> RetentionRun {
+    let table_stats = results
+        .iter()
+        .map(|r| {
+            serde_json::json!({
+                "table": r.table.as_fqn(),
+                "eligible": r.eligible,
+                "rows_would_purge": r.dry_run_rows_older.unwrap_or(0),
+                "deleted_rows": r.deleted_rows,
+                "batches_executed": r.batches_executed,
+                "held_rows_skipped": r.held_rows_skipped,
+                "referenced_rows_skipped": r.referenced_rows_skipped,
+                "lock_timeouts": r.lock_timeouts
+            })
+        })
+        .collect();
+    RetentionRun {
+        run_id,
+        mode,
+        status: RunStatus::Succeeded,
+        started_at,
+        ended_at,
+        duration_ms: (ended_at - started_at).num_milliseconds().max(0),
+        tables: results.len() as i64,
+        rows_would_purge: results.iter().filter_map(|r| r.dry_run_rows_older).sum(),
+        rows_deleted: results.iter().map(|r| r.deleted_rows).sum(),
+        held_rows_skipped: results.iter().map(|r| r.held_rows_skipped).sum::<i64>()
+            + orphans.iter().map(|o| o.held_rows_skipped).sum::<i64>(),
+        referenced_rows_skipped: results.iter().map(|r| r.referenced_rows_skipped).sum(),
+        orphan_rows_deleted: orphans.iter().filter(|o| o.action == OrphanAction::Delete).map(|o| o.rows_deleted).sum(),
+        orphan_rows_marked: orphans.iter().map(|o| o.rows_marked).sum(),
+        lock_timeouts: results.iter().map(|r| r.lock_timeouts).sum(),
+        audit_id: Some(audit_id),
+        error: None,
+        table_stats: JsonValue::Array(table_stats),
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::QualifiedTable;
+    use super::{
+        held_row_predicate, plan_deletion_order, referenced_row_predicate, BatchAdjustment, BatchObservation, BatchSizer,
+        FkDependency, HoldSubject, QualifiedTable, RetentionEnforcerConfig,
+    };
+
+    fn qt(table: &str) -> QualifiedTable {
+        QualifiedTable { schema: "ransomeye".to_string(), table: table.to_string() }
+    }
+
+    fn fk(child: &str, parent: &str) -> FkDependency {
+        FkDependency {
+            constraint: format!("{child}_{parent}_fkey"),
+            child: qt(child),
+            child_columns: vec![format!("{parent}_id")],
+            parent: qt(parent),
+            parent_columns: vec!["id".to_string()],
+            on_delete: "a".to_string(),
+            deferrable: false,
+        }
+    }
+
+    fn sizer(start: i64) -> BatchSizer {
+        BatchSizer::new(&RetentionEnforcerConfig {
+            batch_size: start,
+            min_batch_size: 100,
+            max_batch_size: 10_000,
+            target_batch_ms: 500,
+            lock_timeout_ms: 2000,
+            max_batches_per_table: 200,
+            sleep_ms_between_batches: 0,
+        })
+    }
+
+    fn batch(batch_size: i64, deleted: i64, latency_ms: i64) -> BatchObservation {
+        BatchObservation { batch_size, deleted, latency_ms, lock_waiters: 0, lock_timed_out: false }
+    }
+
+    fn order(policies: &[(QualifiedTable, i64)], deps: &[FkDependency]) -> Vec<String> {
+        plan_deletion_order(policies, deps).order.into_iter().map(|(t, _)| t.table).collect()
+    }
 
     #[test]
     fn parse_qualified_table_accepts_allowed() {
@@ -591,6 +1353,114 @@ mod tests {
         let err = QualifiedTable::quote_ident("x;DROP TABLE y;").unwrap_err();
         assert!(err.contains("illegal identifier"));
     }
-}
 
+    #[test]
+    fn held_predicate_links_every_hold_column() {
+        assert_eq!(held_row_predicate("r", &[]).unwrap(), "FALSE");
+
+        let cols = vec![
+            ("incident_id".to_string(), HoldSubject::Incident),
+            ("primary_entity_id".to_string(), HoldSubject::Entity),
+        ];
+        let sql = held_row_predicate("r", &cols).unwrap();
+        assert!(sql.contains("h.released_at IS NULL"));
+        assert!(sql.contains("(h.subject_type = 'incident' AND h.subject_id = \"r\".\"incident_id\")"));
+        assert!(sql.contains(" OR (h.subject_type = 'entity' AND h.subject_id = \"r\".\"primary_entity_id\")"));
+
+        let bad = vec![("x;DROP".to_string(), HoldSubject::Entity)];
+        assert!(held_row_predicate("r", &bad).is_err());
+    }
+
+    #[test]
+    fn deletion_order_puts_children_first() {
+        let policies = vec![(qt("alerts"), 30), (qt("detections"), 30), (qt("raw_events"), 7)];
+        // raw_events <- detections <- alerts
+        let deps = vec![fk("detections", "raw_events"), fk("alerts", "detections")];
+        assert_eq!(order(&policies, &deps), vec!["alerts", "detections", "raw_events"]);
+
+        let deps = vec![fk("alerts", "raw_events"), fk("raw_events", "detections")];
+        assert_eq!(order(&policies, &deps), vec!["alerts", "raw_events", "detections"]);
 
+        // No dependencies: policy order is kept
+        assert_eq!(order(&policies, &[]), vec!["alerts", "detections", "raw_events"]);
+    }
+
+    #[test]
+    fn deletion_order_ignores_self_and_untargeted_references() {
+        let policies = vec![(qt("incidents"), 90), (qt("raw_events"), 7)];
+        let deps = vec![fk("incidents", "incidents"), fk("evidence", "raw_events")];
+        let plan = plan_deletion_order(&policies, &deps);
+        assert!(plan.cyclic.is_empty());
+        assert_eq!(order(&policies, &deps), vec!["incidents", "raw_events"]);
+    }
+
+    #[test]
+    fn deletion_order_reports_cycles() {
+        let policies = vec![(qt("a"), 1), (qt("b"), 1), (qt("c"), 1), (qt("d"), 1)];
+        // a <-> b, and c is referenced by a so it waits on the cycle; d is unrelated
+        let deps = vec![fk("a", "b"), fk("b", "a"), fk("a", "c")];
+        let plan = plan_deletion_order(&policies, &deps);
+        assert_eq!(plan.cyclic, vec![qt("a"), qt("b"), qt("c")]);
+        assert_eq!(order(&policies, &deps), vec!["d", "a", "b", "c"]);
+    }
+
+    #[test]
+    fn referenced_predicate_joins_every_key_column() {
+        assert_eq!(referenced_row_predicate("r", &[]).unwrap(), "FALSE");
+
+        let mut composite = fk("detections", "raw_events");
+        composite.child_columns = vec!["raw_event_id".to_string(), "tenant_id".to_string()];
+        composite.parent_columns = vec!["event_id".to_string(), "tenant_id".to_string()];
+        let sql = referenced_row_predicate("r", &[composite, fk("alerts", "raw_events")]).unwrap();
+        assert!(sql.contains("EXISTS (SELECT 1 FROM \"ransomeye\".\"detections\" c WHERE c.\"raw_event_id\" = \"r\".\"event_id\" AND c.\"tenant_id\" = \"r\".\"tenant_id\")"));
+        assert!(sql.contains(" OR EXISTS (SELECT 1 FROM \"ransomeye\".\"alerts\" c WHERE c.\"raw_events_id\" = \"r\".\"id\")"));
+
+        let mut bad = fk("alerts", "raw_events");
+        bad.child_columns = vec!["x;DROP".to_string()];
+        assert!(referenced_row_predicate("r", &[bad]).is_err());
+    }
+
+    #[test]
+    fn batch_sizer_steers_towards_target_latency() {
+        let mut s = sizer(1000);
+        // Fast full batch: grows, at most doubling
+        assert_eq!(s.observe(&batch(1000, 1000, 100)), BatchAdjustment::Grow);
+        assert_eq!(s.current(), 2000);
+        // Fast but partial batch says nothing about larger ones
+        assert_eq!(s.observe(&batch(2000, 300, 100)), BatchAdjustment::Hold);
+        assert_eq!(s.current(), 2000);
+        // Within the +-25% band: hold
+        assert_eq!(s.observe(&batch(2000, 2000, 550)), BatchAdjustment::Hold);
+        // Slow: scaled proportionally
+        assert_eq!(s.observe(&batch(2000, 2000, 1000)), BatchAdjustment::Shrink);
+        assert_eq!(s.current(), 1000);
+        // Outlier: at most a 4x cut per step
+        assert_eq!(s.observe(&batch(1000, 1000, 60_000)), BatchAdjustment::Shrink);
+        assert_eq!(s.current(), 250);
+    }
+
+    #[test]
+    fn batch_sizer_backs_off_on_locks_within_bounds() {
+        let mut s = sizer(1000);
+        let mut waited = batch(1000, 1000, 10);
+        waited.lock_waiters = 3;
+        assert_eq!(s.observe(&waited), BatchAdjustment::LockBackoff);
+        assert_eq!(s.current(), 500);
+
+        let timed_out = BatchObservation { batch_size: 500, deleted: 0, latency_ms: 2000, lock_waiters: 0, lock_timed_out: true };
+        assert_eq!(s.observe(&timed_out), BatchAdjustment::LockBackoff);
+        assert_eq!(s.current(), 250);
+        assert_eq!(s.observe(&timed_out), BatchAdjustment::LockBackoff);
+        assert_eq!(s.current(), 125);
+        // Floor reached
+        assert_eq!(s.observe(&timed_out), BatchAdjustment::LockBackoff);
+        assert_eq!(s.current(), 100);
+        assert_eq!(s.observe(&timed_out), BatchAdjustment::Hold);
+        assert_eq!(s.current(), 100);
+
+        let mut s = sizer(8000);
+        assert_eq!(s.observe(&batch(8000, 8000, 1)), BatchAdjustment::Grow);
+        assert_eq!(s.current(), 10_000);
+        assert_eq!(s.observe(&batch(10_000, 10_000, 1)), BatchAdjustment::Hold);
+    }
+}
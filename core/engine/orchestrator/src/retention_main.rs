@@ -1,16 +1,19 @@
 // Path and File Name : /home/ransomeye/rebuild/core/engine/orchestrator/src/retention_main.rs
 // Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
-// Details of functionality of this file: Standalone retention enforcer service binary (periodic runtime purge) with dry-run and fail-closed validation.
+// Details of functionality of this file: Standalone retention enforcer service binary (periodic runtime purge) with dry-run and fail-closed validation, plus the run history listing and the schedule health check with overdue alerts.
 
 use std::process;
 
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 #[path = "lib.rs"]
 mod orchestrator;
 
+use ingest::retention_runs;
 use orchestrator::db::{CoreDb, DbConfig};
-use orchestrator::retention_enforcer::{RetentionEnforcer, RetentionEnforcerConfig};
+use orchestrator::retention_enforcer::{self, RetentionEnforcer, RetentionEnforcerConfig};
+
+const DEFAULT_RUNS_LISTED: i64 = 20;
 
 fn usage_and_exit() -> ! {
     eprintln!("RansomEye Retention Enforcer");
@@ -18,9 +21,14 @@ fn usage_and_exit() -> ! {
     eprintln!("USAGE:");
     eprintln!("  ransomeye_retention_enforcer --dry-run");
     eprintln!("  ransomeye_retention_enforcer --live");
+    eprintln!("  ransomeye_retention_enforcer --runs [<n>]");
+    eprintln!("  ransomeye_retention_enforcer --status [--max-age-hours <n>] [--notify]");
     eprintln!("");
     eprintln!("NOTES:");
     eprintln!("  - Default is FAIL-SAFE: you MUST explicitly choose --live to delete rows.");
+    eprintln!("  - --runs lists the most recent runs (default {}), live and dry.", DEFAULT_RUNS_LISTED);
+    eprintln!("  - --status exits 3 when the last live run failed or no live run succeeded in --max-age-hours");
+    eprintln!("    (default {}); --notify also sends retention.alert webhooks, once per overdue period.", retention_runs::DEFAULT_MAX_AGE_HOURS);
     eprintln!("  - DB env vars are required: DB_HOST, DB_PORT, DB_NAME, DB_USER, DB_PASS");
     process::exit(2);
 }
@@ -29,13 +37,33 @@ fn arg_flag(name: &str) -> bool {
     std::env::args().any(|a| a == name)
 }
 
+fn arg_value(name: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter()
+        .position(|a| a == name)
+        .and_then(|i| args.get(i + 1).cloned())
+        .filter(|v| !v.starts_with("--"))
+}
+
 #[tokio::main]
 async fn main() {
-    tracing_subscriber::fmt::init();
+    orchestrator::BUILD_INFO.handle_version_flag("ransomeye_retention_enforcer");
+    let _otel_guard = kernel::otel::init_tracing("ransomeye-retention-enforcer");
+    let build = &orchestrator::BUILD_INFO;
+    match build.verify_release_from_env("ransomeye_retention_enforcer") {
+        Ok(Some(release)) => info!("Build verified against release {} | commit={}", release, build.git_commit),
+        Ok(None) => warn!("RANSOMEYE_RELEASE_MANIFEST not set; build commit={} not verified against a release", build.git_commit),
+        Err(e) => {
+            error!("FAIL-CLOSED: {e}");
+            process::exit(1);
+        }
+    }
 
     let dry_run = arg_flag("--dry-run");
     let live = arg_flag("--live");
-    if (dry_run && live) || (!dry_run && !live) {
+    let runs = arg_flag("--runs");
+    let status = arg_flag("--status");
+    if [dry_run, live, runs, status].iter().filter(|m| **m).count() != 1 {
         usage_and_exit();
     }
 
@@ -55,17 +83,28 @@ async fn main() {
         }
     };
 
+    if runs {
+        let limit = match arg_value("--runs").map(|v| v.parse::<i64>()) {
+            None => DEFAULT_RUNS_LISTED,
+            Some(Ok(n)) if (1..=1000).contains(&n) => n,
+            Some(_) => usage_and_exit(),
+        };
+        list_runs(&db, limit).await;
+        process::exit(0);
+    }
+
     // Register component for audit attribution (best-effort fail-closed: if this fails, we still abort).
-    let build_hash = std::env::var("RANSOMEYE_BUILD_HASH").ok();
-    let version = std::env::var("RANSOMEYE_VERSION").ok();
+    let build_hash = std::env::var("RANSOMEYE_BUILD_HASH").unwrap_or_else(|_| build.git_commit.to_string());
+    let version = std::env::var("RANSOMEYE_VERSION").unwrap_or_else(|_| build.version.to_string());
     let instance_id = std::env::var("RANSOMEYE_INSTANCE_ID").ok();
     let component_id = match db
         .upsert_component(
             "db_core",
             "ransomeye_retention_enforcer",
             instance_id.as_deref(),
-            build_hash.as_deref(),
-            version.as_deref(),
+            Some(&build_hash),
+            Some(&version),
+            Some(&build.metadata()),
         )
         .await
     {
@@ -76,6 +115,18 @@ async fn main() {
         }
     };
 
+    if status {
+        let max_age_hours = match arg_value("--max-age-hours").map(|v| v.parse::<i64>()) {
+            None => retention_runs::DEFAULT_MAX_AGE_HOURS,
+            Some(Ok(n)) => match retention_runs::check_max_age_hours(n) {
+                Ok(n) => n,
+                Err(_) => usage_and_exit(),
+            },
+            Some(Err(_)) => usage_and_exit(),
+        };
+        check_schedule(&db, component_id, max_age_hours, arg_flag("--notify")).await;
+    }
+
     let enforcer_cfg = match RetentionEnforcerConfig::from_env() {
         Ok(c) => c,
         Err(e) => {
@@ -86,9 +137,12 @@ async fn main() {
     let enforcer = RetentionEnforcer::new(enforcer_cfg.clone());
 
     info!(
-        "Retention enforcer starting (mode={}, batch_size={}, max_batches_per_table={})",
+        "Retention enforcer starting (mode={}, batch_size={} [{}..={}], target_batch_ms={}, max_batches_per_table={})",
         if dry_run { "DRY-RUN" } else { "LIVE" },
         enforcer_cfg.batch_size,
+        enforcer_cfg.min_batch_size,
+        enforcer_cfg.max_batch_size,
+        enforcer_cfg.target_batch_ms,
         enforcer_cfg.max_batches_per_table
     );
 
@@ -132,4 +186,65 @@ async fn main() {
     process::exit(0);
 }
 
+async fn list_runs(db: &CoreDb, limit: i64) {
+    let runs = match retention_runs::recent(db.client(), limit).await {
+        Ok(r) => r,
+        Err(e) => {
+            error!("FAIL-CLOSED: {e}");
+            process::exit(1);
+        }
+    };
+    if runs.is_empty() {
+        info!("[RETENTION] no runs recorded");
+    }
+    for r in runs {
+        info!(
+            "[RETENTION] run_id={} mode={} status={} started_at={} duration_ms={} tables={} would_purge_rows={} deleted_rows={} held_rows_skipped={} orphan_rows_deleted={} lock_timeouts={}{}",
+            r.run_id,
+            r.mode.as_str(),
+            r.status.as_str(),
+            r.started_at.to_rfc3339(),
+            r.duration_ms,
+            r.tables,
+            r.rows_would_purge,
+            r.rows_deleted,
+            r.held_rows_skipped,
+            r.orphan_rows_deleted,
+            r.lock_timeouts,
+            r.error.map(|e| format!(" error={e}")).unwrap_or_default()
+        );
+    }
+}
 
+/// Exits 0 when the schedule is healthy, 3 when it raised alerts and 1 when it could not be checked.
+async fn check_schedule(db: &CoreDb, component_id: uuid::Uuid, max_age_hours: i64, notify: bool) -> ! {
+    let (live, last_success) = match retention_runs::live_since_last_success(db.client()).await {
+        Ok(r) => r,
+        Err(e) => {
+            error!("FAIL-CLOSED: {e}");
+            process::exit(1);
+        }
+    };
+    let status = retention_runs::assess(&live, last_success, max_age_hours, chrono::Utc::now());
+    match &status.last_success {
+        Some(r) => info!("[RETENTION] last successful live run {} ended {}", r.run_id, r.ended_at.to_rfc3339()),
+        None => info!("[RETENTION] no successful live run recorded"),
+    }
+    for alert in &status.alerts {
+        warn!("[RETENTION] ALERT {}: {}", alert.kind.as_str(), alert.message);
+    }
+    if notify {
+        match retention_enforcer::notify_schedule_alerts(db, Some(component_id), &status).await {
+            Ok(sent) => info!("[RETENTION] {} new alert(s) sent", sent),
+            Err(e) => {
+                error!("FAIL-CLOSED: {e}");
+                process::exit(1);
+            }
+        }
+    }
+    if status.healthy {
+        info!("[RETENTION] schedule healthy (max_age_hours={})", max_age_hours);
+        process::exit(0);
+    }
+    process::exit(3);
+}
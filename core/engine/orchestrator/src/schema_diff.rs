@@ -0,0 +1,446 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/orchestrator/src/schema_diff.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Structured diff between the authoritative schema file and the live ransomeye schema (tables, columns, column types, enum labels, indexes) for remediation reporting.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+
+use serde::Serialize;
+
+use super::db::CoreDb;
+
+/// Objects declared by the authoritative schema (or observed in the live DB).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaModel {
+    /// table -> (column -> canonical type)
+    pub tables: BTreeMap<String, BTreeMap<String, String>>,
+    /// enum type -> labels in declaration order
+    pub enums: BTreeMap<String, Vec<String>>,
+    pub indexes: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ColumnRef {
+    pub table: String,
+    pub column: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TypeMismatch {
+    pub table: String,
+    pub column: String,
+    pub expected: String,
+    pub actual: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EnumLabelDiff {
+    pub enum_type: String,
+    pub missing_labels: Vec<String>,
+    pub extra_labels: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SchemaDiff {
+    pub missing_tables: Vec<String>,
+    pub extra_tables: Vec<String>,
+    pub missing_columns: Vec<ColumnRef>,
+    pub extra_columns: Vec<ColumnRef>,
+    pub type_mismatches: Vec<TypeMismatch>,
+    pub missing_enum_types: Vec<String>,
+    pub extra_enum_types: Vec<String>,
+    pub enum_label_diffs: Vec<EnumLabelDiff>,
+    pub missing_indexes: Vec<String>,
+    pub extra_indexes: Vec<String>,
+}
+
+impl SchemaDiff {
+    pub fn is_empty(&self) -> bool {
+        self == &SchemaDiff::default()
+    }
+
+    /// Human-readable report, grouped by object kind (remediation order: types, tables, columns, indexes).
+    pub fn render_text(&self) -> String {
+        if self.is_empty() {
+            return "Schema matches the authoritative schema file (no differences).\n".to_string();
+        }
+        let mut out = String::new();
+        let mut section = |title: &str, items: Vec<String>| {
+            if items.is_empty() {
+                return;
+            }
+            let _ = writeln!(out, "{} ({}):", title, items.len());
+            for i in items {
+                let _ = writeln!(out, "  - {}", i);
+            }
+        };
+        section("Missing enum types", self.missing_enum_types.clone());
+        section(
+            "Enum label differences",
+            self.enum_label_diffs
+                .iter()
+                .map(|d| format!("{}: missing={:?} extra={:?}", d.enum_type, d.missing_labels, d.extra_labels))
+                .collect(),
+        );
+        section("Missing tables", self.missing_tables.clone());
+        section(
+            "Missing columns",
+            self.missing_columns.iter().map(|c| format!("{}.{}", c.table, c.column)).collect(),
+        );
+        section(
+            "Type mismatches",
+            self.type_mismatches
+                .iter()
+                .map(|m| format!("{}.{}: expected {}, found {}", m.table, m.column, m.expected, m.actual))
+                .collect(),
+        );
+        section("Missing indexes", self.missing_indexes.clone());
+        section("Extra tables (not in authoritative schema)", self.extra_tables.clone());
+        section(
+            "Extra columns (not in authoritative schema)",
+            self.extra_columns.iter().map(|c| format!("{}.{}", c.table, c.column)).collect(),
+        );
+        section("Extra enum types (not in authoritative schema)", self.extra_enum_types.clone());
+        section("Extra indexes (not in authoritative schema)", self.extra_indexes.clone());
+        out
+    }
+}
+
+/// Parse CREATE TYPE ... AS ENUM, CREATE TABLE IF NOT EXISTS, and CREATE [UNIQUE] INDEX statements.
+pub fn parse_authoritative_schema(sql: &str) -> SchemaModel {
+    let mut model = SchemaModel::default();
+    let lines: Vec<&str> = sql.lines().collect();
+    let mut i = 0;
+    while i < lines.len() {
+        let trimmed = lines[i].trim();
+
+        if let Some(rest) = trimmed.strip_prefix("CREATE TYPE ") {
+            if let Some((name, _)) = rest.split_once(" AS ENUM") {
+                // Labels may span lines until the closing ");".
+                let mut body = String::from(rest);
+                while !body.trim_end().ends_with(");") && i + 1 < lines.len() {
+                    i += 1;
+                    body.push(' ');
+                    body.push_str(lines[i].trim());
+                }
+                model.enums.insert(name.trim().to_string(), quoted_literals(&body));
+            }
+        } else if let Some(rest) = trimmed.strip_prefix("CREATE TABLE IF NOT EXISTS ") {
+            let table = rest.split_whitespace().next().unwrap_or("").to_string();
+            let mut columns = BTreeMap::new();
+            i += 1;
+            while i < lines.len() && lines[i].trim() != ");" {
+                if let Some((col, ty)) = parse_column_line(lines[i]) {
+                    columns.insert(col, ty);
+                }
+                i += 1;
+            }
+            model.tables.insert(table, columns);
+        } else if trimmed.starts_with("CREATE INDEX ") || trimmed.starts_with("CREATE UNIQUE INDEX ") {
+            let name = trimmed
+                .split_whitespace()
+                .skip_while(|t| *t != "INDEX")
+                .skip(1)
+                .find(|t| !matches!(*t, "IF" | "NOT" | "EXISTS" | "CONCURRENTLY"));
+            if let Some(name) = name {
+                model.indexes.insert(name.to_string());
+            }
+        }
+        i += 1;
+    }
+    model
+}
+
+fn parse_column_line(line: &str) -> Option<(String, String)> {
+    let trimmed = line.trim();
+    let name = trimmed.split_whitespace().next()?;
+    let is_ident = name
+        .chars()
+        .next()
+        .map(|c| c.is_ascii_lowercase() || c == '_')
+        .unwrap_or(false)
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if !is_ident {
+        // CONSTRAINT/CHECK/PRIMARY KEY lines, comments, and multi-line expression continuations.
+        return None;
+    }
+
+    // Column definition ends at the first top-level comma (commas inside numeric(p, s) do not count).
+    let rest = trimmed[name.len()..].trim();
+    let mut depth = 0i32;
+    let mut end = rest.len();
+    for (idx, ch) in rest.char_indices() {
+        match ch {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                end = idx;
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    let ty_tokens: Vec<&str> = rest[..end]
+        .split_whitespace()
+        .take_while(|t| {
+            !matches!(
+                t.to_ascii_uppercase().as_str(),
+                "NOT" | "NULL" | "PRIMARY" | "DEFAULT" | "REFERENCES" | "CHECK" | "UNIQUE" | "CONSTRAINT" | "GENERATED" | "COLLATE"
+            )
+        })
+        .collect();
+    if ty_tokens.is_empty() {
+        return None;
+    }
+    Some((name.to_string(), canonical_type(&ty_tokens.join(" "))))
+}
+
+/// Map declared types to PostgreSQL `format_type()` output so file and live types compare directly.
+pub fn canonical_type(declared: &str) -> String {
+    let t = declared.trim().to_ascii_lowercase();
+    let t = t.strip_prefix("ransomeye.").unwrap_or(&t).to_string();
+    let (base, array) = match t.strip_suffix("[]") {
+        Some(b) => (b.trim().to_string(), "[]"),
+        None => (t.clone(), ""),
+    };
+    let (head, args) = match base.split_once('(') {
+        Some((h, a)) => (h.trim().to_string(), format!("({}", a.replace(' ', ""))),
+        None => (base.clone(), String::new()),
+    };
+    let head = match head.as_str() {
+        "int" | "int4" | "integer" | "serial" | "serial4" => "integer",
+        "bigint" | "int8" | "bigserial" | "serial8" => "bigint",
+        "smallint" | "int2" | "smallserial" | "serial2" => "smallint",
+        "bool" | "boolean" => "boolean",
+        "float8" | "double precision" => "double precision",
+        "float4" | "real" => "real",
+        "timestamptz" | "timestamp with time zone" => "timestamp with time zone",
+        "timestamp" | "timestamp without time zone" => "timestamp without time zone",
+        "varchar" | "character varying" => "character varying",
+        "char" | "character" | "bpchar" => "character",
+        "decimal" | "numeric" => "numeric",
+        other => other,
+    };
+    format!("{}{}{}", head, args, array)
+}
+
+fn quoted_literals(s: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut rest = s;
+    while let Some(start) = rest.find('\'') {
+        let after = &rest[start + 1..];
+        match after.find('\'') {
+            Some(end) => {
+                out.push(after[..end].to_string());
+                rest = &after[end + 1..];
+            }
+            None => break,
+        }
+    }
+    out
+}
+
+pub fn diff_models(expected: &SchemaModel, actual: &SchemaModel) -> SchemaDiff {
+    let mut d = SchemaDiff::default();
+
+    for (table, cols) in &expected.tables {
+        let Some(live_cols) = actual.tables.get(table) else {
+            d.missing_tables.push(table.clone());
+            continue;
+        };
+        for (col, ty) in cols {
+            match live_cols.get(col) {
+                None => d.missing_columns.push(ColumnRef { table: table.clone(), column: col.clone() }),
+                Some(live_ty) if live_ty != ty => d.type_mismatches.push(TypeMismatch {
+                    table: table.clone(),
+                    column: col.clone(),
+                    expected: ty.clone(),
+                    actual: live_ty.clone(),
+                }),
+                Some(_) => {}
+            }
+        }
+        for col in live_cols.keys() {
+            if !cols.contains_key(col) {
+                d.extra_columns.push(ColumnRef { table: table.clone(), column: col.clone() });
+            }
+        }
+    }
+    d.extra_tables = actual.tables.keys().filter(|t| !expected.tables.contains_key(*t)).cloned().collect();
+
+    for (name, labels) in &expected.enums {
+        let Some(live) = actual.enums.get(name) else {
+            d.missing_enum_types.push(name.clone());
+            continue;
+        };
+        let missing: Vec<String> = labels.iter().filter(|l| !live.contains(l)).cloned().collect();
+        let extra: Vec<String> = live.iter().filter(|l| !labels.contains(l)).cloned().collect();
+        if !missing.is_empty() || !extra.is_empty() {
+            d.enum_label_diffs.push(EnumLabelDiff {
+                enum_type: name.clone(),
+                missing_labels: missing,
+                extra_labels: extra,
+            });
+        }
+    }
+    d.extra_enum_types = actual.enums.keys().filter(|t| !expected.enums.contains_key(*t)).cloned().collect();
+
+    d.missing_indexes = expected.indexes.difference(&actual.indexes).cloned().collect();
+    d.extra_indexes = actual.indexes.difference(&expected.indexes).cloned().collect();
+    d
+}
+
+/// Read tables/columns (format_type), enum labels, and non-constraint indexes from the live `ransomeye` schema.
+pub async fn load_live_model(db: &CoreDb) -> Result<SchemaModel, String> {
+    let mut model = SchemaModel::default();
+
+    let rows = db
+        .client()
+        .query(
+            r#"
+            SELECT c.relname::text, a.attname::text, format_type(a.atttypid, a.atttypmod)
+            FROM pg_attribute a
+            JOIN pg_class c ON c.oid = a.attrelid
+            JOIN pg_namespace n ON n.oid = c.relnamespace
+            WHERE n.nspname = 'ransomeye'
+              AND c.relkind IN ('r', 'p')
+              AND a.attnum > 0
+              AND NOT a.attisdropped
+            "#,
+            &[],
+        )
+        .await
+        .map_err(|e| format!("Schema diff failed reading live columns: {e}"))?;
+    for r in rows {
+        let table: String = r.get(0);
+        let column: String = r.get(1);
+        let ty: String = r.get(2);
+        model.tables.entry(table).or_default().insert(column, canonical_type(&ty));
+    }
+    // Tables without columns still count as present.
+    let rows = db
+        .client()
+        .query(
+            "SELECT table_name::text FROM information_schema.tables WHERE table_schema = 'ransomeye' AND table_type = 'BASE TABLE'",
+            &[],
+        )
+        .await
+        .map_err(|e| format!("Schema diff failed reading live tables: {e}"))?;
+    for r in rows {
+        let table: String = r.get(0);
+        model.tables.entry(table).or_default();
+    }
+
+    let rows = db
+        .client()
+        .query(
+            r#"
+            SELECT t.typname::text, e.enumlabel::text
+            FROM pg_enum e
+            JOIN pg_type t ON t.oid = e.enumtypid
+            JOIN pg_namespace n ON n.oid = t.typnamespace
+            WHERE n.nspname = 'ransomeye'
+            ORDER BY t.typname, e.enumsortorder
+            "#,
+            &[],
+        )
+        .await
+        .map_err(|e| format!("Schema diff failed reading live enums: {e}"))?;
+    for r in rows {
+        let name: String = r.get(0);
+        let label: String = r.get(1);
+        model.enums.entry(name).or_default().push(label);
+    }
+
+    // Constraint-backed indexes (PKs, UNIQUE constraints) are implied by table DDL, not declared as indexes.
+    let rows = db
+        .client()
+        .query(
+            r#"
+            SELECT i.indexname::text
+            FROM pg_indexes i
+            WHERE i.schemaname = 'ransomeye'
+              AND NOT EXISTS (
+                SELECT 1 FROM pg_constraint con
+                JOIN pg_namespace n ON n.oid = con.connamespace
+                WHERE n.nspname = 'ransomeye' AND con.conname = i.indexname
+              )
+            "#,
+            &[],
+        )
+        .await
+        .map_err(|e| format!("Schema diff failed reading live indexes: {e}"))?;
+    for r in rows {
+        let name: String = r.get(0);
+        model.indexes.insert(name);
+    }
+
+    Ok(model)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SQL: &str = r#"
+CREATE TYPE severity_level AS ENUM ('debug', 'info');
+CREATE TYPE event_source_type AS ENUM (
+  'linux_agent',
+  'dpi_probe'
+);
+CREATE TABLE IF NOT EXISTS samples (
+  sample_id              uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+  severity               severity_level NOT NULL,
+  score                  double precision NULL,
+  label                  varchar(36),
+  amount                 numeric(5, 2) NOT NULL,
+  seen_at                timestamptz NOT NULL DEFAULT now(),
+  -- comment line
+  CONSTRAINT samples_chk CHECK (
+    (score IS NULL OR score >= 0)
+  )
+);
+CREATE INDEX IF NOT EXISTS idx_samples_seen_at ON samples (seen_at);
+CREATE UNIQUE INDEX samples_label_uniq_idx
+ON samples (label);
+"#;
+
+    #[test]
+    fn parses_tables_enums_indexes() {
+        let m = parse_authoritative_schema(SQL);
+        let cols = &m.tables["samples"];
+        assert_eq!(cols.len(), 6);
+        assert_eq!(cols["score"], "double precision");
+        assert_eq!(cols["label"], "character varying(36)");
+        assert_eq!(cols["amount"], "numeric(5,2)");
+        assert_eq!(cols["seen_at"], "timestamp with time zone");
+        assert_eq!(cols["severity"], "severity_level");
+        assert_eq!(m.enums["event_source_type"], vec!["linux_agent", "dpi_probe"]);
+        assert!(m.indexes.contains("idx_samples_seen_at"));
+        assert!(m.indexes.contains("samples_label_uniq_idx"));
+    }
+
+    #[test]
+    fn diff_reports_all_categories() {
+        let expected = parse_authoritative_schema(SQL);
+        let mut actual = expected.clone();
+        let cols = actual.tables.get_mut("samples").unwrap();
+        cols.remove("label");
+        cols.insert("score".to_string(), "real".to_string());
+        cols.insert("legacy".to_string(), "text".to_string());
+        actual.enums.get_mut("severity_level").unwrap().push("trace".to_string());
+        actual.indexes.remove("idx_samples_seen_at");
+        actual.tables.insert("scratch".to_string(), BTreeMap::new());
+
+        let d = diff_models(&expected, &actual);
+        assert_eq!(d.missing_columns, vec![ColumnRef { table: "samples".into(), column: "label".into() }]);
+        assert_eq!(d.extra_columns, vec![ColumnRef { table: "samples".into(), column: "legacy".into() }]);
+        assert_eq!(d.type_mismatches.len(), 1);
+        assert_eq!(d.enum_label_diffs[0].extra_labels, vec!["trace".to_string()]);
+        assert_eq!(d.missing_indexes, vec!["idx_samples_seen_at".to_string()]);
+        assert_eq!(d.extra_tables, vec!["scratch".to_string()]);
+        assert!(!d.is_empty());
+        assert!(diff_models(&expected, &expected).is_empty());
+    }
+}
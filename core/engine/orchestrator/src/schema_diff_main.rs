@@ -0,0 +1,102 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/orchestrator/src/schema_diff_main.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Standalone schema-diff tool binary - compares the live ransomeye schema against the authoritative schema file and prints a structured diff (JSON or text).
+
+use std::process;
+
+use tracing::error;
+
+#[path = "lib.rs"]
+mod orchestrator;
+
+use orchestrator::db::{CoreDb, DbConfig};
+use orchestrator::schema_diff;
+
+fn usage_and_exit() -> ! {
+    eprintln!("RansomEye Schema Diff");
+    eprintln!("");
+    eprintln!("USAGE:");
+    eprintln!("  ransomeye_schema_diff [--schema-file <schema.sql>] [--format text|json]");
+    eprintln!("");
+    eprintln!("NOTES:");
+    eprintln!("  - Read-only: never modifies the database.");
+    eprintln!("  - --schema-file defaults to RANSOMEYE_SCHEMA_SQL_PATH");
+    eprintln!("  - DB env vars are required: DB_HOST, DB_PORT, DB_NAME, DB_USER, DB_PASS");
+    eprintln!("  - Exit code 0 = schema matches, 3 = differences found");
+    process::exit(2);
+}
+
+fn arg_value(name: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter()
+        .position(|a| a == name)
+        .and_then(|i| args.get(i + 1).cloned())
+}
+
+#[tokio::main]
+async fn main() {
+    orchestrator::BUILD_INFO.handle_version_flag("ransomeye_schema_diff");
+    tracing_subscriber::fmt().with_writer(std::io::stderr).init();
+
+    if std::env::args().any(|a| a == "--help" || a == "-h") {
+        usage_and_exit();
+    }
+
+    let json = match arg_value("--format").as_deref() {
+        None | Some("text") => false,
+        Some("json") => true,
+        Some(_) => usage_and_exit(),
+    };
+
+    let Some(schema_path) = arg_value("--schema-file").or_else(|| std::env::var("RANSOMEYE_SCHEMA_SQL_PATH").ok()) else {
+        usage_and_exit();
+    };
+
+    let sql = match std::fs::read_to_string(&schema_path) {
+        Ok(s) => s,
+        Err(e) => {
+            error!("FAIL-CLOSED: Failed to read authoritative schema file at {}: {}", schema_path, e);
+            process::exit(1);
+        }
+    };
+    let expected = schema_diff::parse_authoritative_schema(&sql);
+
+    let cfg = match DbConfig::from_env_strict() {
+        Ok(c) => c,
+        Err(e) => {
+            error!("{e}");
+            process::exit(1);
+        }
+    };
+
+    let db = match CoreDb::connect_strict(&cfg).await {
+        Ok(db) => db,
+        Err(e) => {
+            error!("FAIL-CLOSED: DB connect failed: {e}");
+            process::exit(1);
+        }
+    };
+
+    let actual = match schema_diff::load_live_model(&db).await {
+        Ok(m) => m,
+        Err(e) => {
+            error!("{e}");
+            process::exit(1);
+        }
+    };
+
+    let diff = schema_diff::diff_models(&expected, &actual);
+    if json {
+        match serde_json::to_string_pretty(&diff) {
+            Ok(s) => println!("{s}"),
+            Err(e) => {
+                error!("Failed to serialize schema diff: {e}");
+                process::exit(1);
+            }
+        }
+    } else {
+        print!("{}", diff.render_text());
+    }
+
+    process::exit(if diff.is_empty() { 0 } else { 3 });
+}
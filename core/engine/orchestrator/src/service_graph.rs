@@ -0,0 +1,274 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/orchestrator/src/service_graph.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Dependency graph of orchestrator subsystems and managed services - derives startup and shutdown order topologically and reports missing dependencies and cycles precisely
+
+/*
+ * Service Dependency Graph
+ *
+ * Every startup step is a node that names the nodes it depends on. Subsystems (storage, trust,
+ * policy, bus, ...) are declared in code by the orchestrator; managed services are the required
+ * services (RANSOMEYE_REQUIRED_SERVICES), whose dependencies come from
+ * RANSOMEYE_SERVICE_DEPENDENCIES:
+ *
+ *   RANSOMEYE_SERVICE_DEPENDENCIES=ransomeye_reporting:ransomeye_ingestion,bus;ransomeye_ingestion:storage
+ *
+ * Startup order is a topological order; among nodes whose dependencies are all started, the one
+ * declared first goes first, so the order is stable for a given declaration. Shutdown is the
+ * exact reverse. A dependency on an undeclared node, a node declared twice and a cycle are
+ * reported in full (every missing edge; the nodes of the cycle in dependency order) and the
+ * orchestrator refuses to start.
+ */
+
+use std::collections::{BTreeSet, HashMap};
+
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    /// Orchestrator-internal step, declared in code
+    Subsystem,
+    /// Separately run service the orchestrator waits for
+    Service,
+}
+
+impl NodeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NodeKind::Subsystem => "subsystem",
+            NodeKind::Service => "service",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    pub name: String,
+    pub kind: NodeKind,
+    pub depends_on: Vec<String>,
+}
+
+/// An edge to a node that is not declared.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingDependency {
+    pub node: String,
+    pub dependency: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum GraphError {
+    #[error("node '{0}' declared more than once")]
+    Duplicate(String),
+    #[error("missing dependencies: {}", .0.iter().map(|m| format!("{} -> {}", m.node, m.dependency)).collect::<Vec<_>>().join(", "))]
+    Missing(Vec<MissingDependency>),
+    /// Nodes of one cycle, each depending on the next; the first is repeated at the end
+    #[error("dependency cycle: {}", .0.join(" -> "))]
+    Cycle(Vec<String>),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ServiceGraph {
+    nodes: Vec<Node>,
+}
+
+impl ServiceGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn add(&mut self, name: &str, kind: NodeKind, depends_on: &[&str]) -> &mut Self {
+        self.nodes.push(Node {
+            name: name.to_string(),
+            kind,
+            depends_on: depends_on.iter().map(|d| d.to_string()).collect(),
+        });
+        self
+    }
+
+    pub fn subsystem(&mut self, name: &str, depends_on: &[&str]) -> &mut Self {
+        self.add(name, NodeKind::Subsystem, depends_on)
+    }
+
+    pub fn service(&mut self, name: &str, depends_on: &[&str]) -> &mut Self {
+        self.add(name, NodeKind::Service, depends_on)
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Node> {
+        self.nodes.iter().find(|n| n.name == name)
+    }
+
+    /// Add dependencies to a declared node (duplicates are ignored).
+    pub fn add_dependencies(&mut self, name: &str, depends_on: &[String]) -> Result<(), String> {
+        let node = self
+            .nodes
+            .iter_mut()
+            .find(|n| n.name == name)
+            .ok_or_else(|| format!("'{}' is not a declared node", name))?;
+        for dep in depends_on {
+            if !node.depends_on.contains(dep) {
+                node.depends_on.push(dep.clone());
+            }
+        }
+        Ok(())
+    }
+
+    /// Nodes in startup order: every node after all of its dependencies.
+    pub fn startup_order(&self) -> Result<Vec<&Node>, GraphError> {
+        let mut index: HashMap<&str, usize> = HashMap::new();
+        for (i, node) in self.nodes.iter().enumerate() {
+            if index.insert(node.name.as_str(), i).is_some() {
+                return Err(GraphError::Duplicate(node.name.clone()));
+            }
+        }
+        let missing: Vec<MissingDependency> = self
+            .nodes
+            .iter()
+            .flat_map(|n| n.depends_on.iter().map(move |d| (n, d)))
+            .filter(|(_, d)| !index.contains_key(d.as_str()))
+            .map(|(n, d)| MissingDependency { node: n.name.clone(), dependency: d.clone() })
+            .collect();
+        if !missing.is_empty() {
+            return Err(GraphError::Missing(missing));
+        }
+
+        let deps: Vec<BTreeSet<usize>> = self
+            .nodes
+            .iter()
+            .map(|n| n.depends_on.iter().map(|d| index[d.as_str()]).collect())
+            .collect();
+        let mut started = vec![false; self.nodes.len()];
+        let mut order = Vec::with_capacity(self.nodes.len());
+        // Each pass starts the first declared node that is ready; declaration order breaks ties
+        while let Some(next) = (0..self.nodes.len()).find(|&i| !started[i] && deps[i].iter().all(|&d| started[d])) {
+            started[next] = true;
+            order.push(&self.nodes[next]);
+        }
+        if order.len() == self.nodes.len() {
+            return Ok(order);
+        }
+
+        // Every node left has a dependency left: follow the first one until a node repeats
+        let first = (0..self.nodes.len()).find(|&i| !started[i]).expect("a node is left");
+        let mut path = vec![first];
+        loop {
+            let current = *path.last().expect("path is not empty");
+            let dep = *deps[current].iter().find(|&&d| !started[d]).expect("an unstarted node has an unstarted dependency");
+            if let Some(pos) = path.iter().position(|&p| p == dep) {
+                let mut cycle: Vec<String> = path[pos..].iter().map(|&i| self.nodes[i].name.clone()).collect();
+                cycle.push(self.nodes[dep].name.clone());
+                return Err(GraphError::Cycle(cycle));
+            }
+            path.push(dep);
+        }
+    }
+
+    /// Nodes in shutdown order: the reverse of startup.
+    pub fn shutdown_order(&self) -> Result<Vec<&Node>, GraphError> {
+        let mut order = self.startup_order()?;
+        order.reverse();
+        Ok(order)
+    }
+}
+
+/// Parse RANSOMEYE_SERVICE_DEPENDENCIES: `service:dep,dep;service:dep`.
+pub fn parse_dependencies(spec: &str) -> Result<Vec<(String, Vec<String>)>, String> {
+    let mut parsed: Vec<(String, Vec<String>)> = Vec::new();
+    for entry in spec.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+        let (service, deps) = entry
+            .split_once(':')
+            .ok_or_else(|| format!("'{}' is not service:dependency[,dependency...]", entry))?;
+        let service = service.trim();
+        if service.is_empty() {
+            return Err(format!("'{}' names no service", entry));
+        }
+        if parsed.iter().any(|(s, _)| s == service) {
+            return Err(format!("service '{}' listed more than once", service));
+        }
+        let deps: Vec<String> = deps.split(',').map(|d| d.trim().to_string()).filter(|d| !d.is_empty()).collect();
+        if deps.is_empty() {
+            return Err(format!("service '{}' lists no dependencies", service));
+        }
+        parsed.push((service.to_string(), deps));
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(order: &[&Node]) -> Vec<String> {
+        order.iter().map(|n| n.name.clone()).collect()
+    }
+
+    #[test]
+    fn startup_order_follows_dependencies_then_declaration() {
+        let mut graph = ServiceGraph::new();
+        graph
+            .subsystem("environment", &[])
+            .subsystem("bus", &["trust"])
+            .subsystem("storage", &["environment"])
+            .subsystem("trust", &["storage"])
+            .service("ransomeye_ingestion", &["bus", "storage"]);
+        let startup = graph.startup_order().unwrap();
+        assert_eq!(names(&startup), ["environment", "storage", "trust", "bus", "ransomeye_ingestion"]);
+        let shutdown = graph.shutdown_order().unwrap();
+        assert_eq!(names(&shutdown), ["ransomeye_ingestion", "bus", "trust", "storage", "environment"]);
+    }
+
+    #[test]
+    fn missing_dependencies_are_all_reported() {
+        let mut graph = ServiceGraph::new();
+        graph
+            .subsystem("environment", &[])
+            .service("ransomeye_reporting", &["ransomeye_ingestion", "environment"])
+            .service("ransomeye_dispatch", &["bus"]);
+        let err = graph.startup_order().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "missing dependencies: ransomeye_reporting -> ransomeye_ingestion, ransomeye_dispatch -> bus"
+        );
+    }
+
+    #[test]
+    fn cycle_is_reported_in_dependency_order() {
+        let mut graph = ServiceGraph::new();
+        graph
+            .subsystem("environment", &[])
+            .service("a", &["environment", "b"])
+            .service("b", &["c"])
+            .service("c", &["a"])
+            .service("d", &["a"]);
+        assert_eq!(
+            graph.startup_order().unwrap_err(),
+            GraphError::Cycle(vec!["a".into(), "b".into(), "c".into(), "a".into()])
+        );
+
+        let mut graph = ServiceGraph::new();
+        graph.subsystem("environment", &["environment"]);
+        assert_eq!(graph.startup_order().unwrap_err().to_string(), "dependency cycle: environment -> environment");
+
+        let mut graph = ServiceGraph::new();
+        graph.subsystem("bus", &[]).service("bus", &[]);
+        assert_eq!(graph.startup_order().unwrap_err(), GraphError::Duplicate("bus".into()));
+    }
+
+    #[test]
+    fn parses_service_dependencies() {
+        let parsed = parse_dependencies(" ransomeye_reporting: ransomeye_ingestion , bus ; ransomeye_ingestion:storage;").unwrap();
+        assert_eq!(
+            parsed,
+            vec![
+                ("ransomeye_reporting".to_string(), vec!["ransomeye_ingestion".to_string(), "bus".to_string()]),
+                ("ransomeye_ingestion".to_string(), vec!["storage".to_string()]),
+            ]
+        );
+        assert!(parse_dependencies("").unwrap().is_empty());
+        assert!(parse_dependencies("ransomeye_reporting").is_err());
+        assert!(parse_dependencies("ransomeye_reporting:").is_err());
+        assert!(parse_dependencies("a:b;a:c").is_err());
+    }
+}
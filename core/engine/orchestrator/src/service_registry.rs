@@ -0,0 +1,709 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/orchestrator/src/service_registry.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Service registry fed by heartbeats from separately run core services (ingest), with the orchestrator status listener (POST /v1/services/heartbeat, GET /v1/status) and liveness transitions for the health gate
+
+/*
+ * Service Registry
+ *
+ * Core services report with ingest::service_heartbeat. Each (service, instance_id) entry keeps the
+ * last heartbeat; it is live while the last one is younger than RANSOMEYE_SERVICE_STALE_AFTER_SECS.
+ * A required service (RANSOMEYE_REQUIRED_SERVICES) is up when at least one live instance reports
+ * ready or degraded. A new boot_id is a restart; during a restart handoff the draining predecessor
+ * keeps heartbeating with its old boot_id, which is acknowledged but not recorded.
+ *
+ * Heartbeats may carry the service's health report (health::HealthReport); GET /v1/status answers
+ * with the orchestrator's own report in the same model, whose subsystems are its lifecycle, config
+ * drift and every required service.
+ *
+ * Heartbeat handlers only touch memory. Transitions (registered, restarted, stale, recovered) are
+ * queued and drained by the orchestrator's run loop, which writes them to the audit log and
+ * component_health on its own connection in order with its other writes.
+ */
+
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::Json,
+    routing::{get, post},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use health::{HealthReport, HealthStatus};
+use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use ingest::service_heartbeat::{self, Heartbeat, HeartbeatAck, ServiceStatus};
+
+use super::capacity_forecast::{CapacityForecast, CapacityLevel};
+use super::OrchestratorState;
+
+const DEFAULT_STATUS_ADDR: &str = "127.0.0.1:8091";
+const COMPONENT: &str = "ransomeye_orchestrator";
+
+#[derive(Debug, Clone)]
+pub struct ServiceRegistryConfig {
+    /// Status/heartbeat listener (RANSOMEYE_ORCHESTRATOR_STATUS_ADDR); None = disabled ("off")
+    pub listen_addr: Option<SocketAddr>,
+    /// Shared bearer token (RANSOMEYE_SERVICE_HEARTBEAT_TOKEN_PATH)
+    pub token: Option<String>,
+    pub required: Vec<String>,
+    /// Startup dependencies of required services (RANSOMEYE_SERVICE_DEPENDENCIES), see service_graph.rs
+    pub dependencies: Vec<(String, Vec<String>)>,
+    pub stale_after: Duration,
+    /// How long the health gate waits for required services to report ready
+    pub registration_wait: Duration,
+    pub check_interval: Duration,
+}
+
+fn env_u64(key: &str, default_value: u64) -> Result<u64, String> {
+    match std::env::var(key) {
+        Ok(v) => v
+            .parse::<u64>()
+            .ok()
+            .filter(|n| *n > 0)
+            .ok_or_else(|| format!("Invalid {}='{}' (expected integer > 0)", key, v)),
+        Err(_) => Ok(default_value),
+    }
+}
+
+impl ServiceRegistryConfig {
+    pub fn from_env() -> Result<Self, String> {
+        let listen_addr = match std::env::var("RANSOMEYE_ORCHESTRATOR_STATUS_ADDR") {
+            Ok(v) if v.is_empty() || v.eq_ignore_ascii_case("off") => None,
+            Ok(v) => Some(
+                v.parse::<SocketAddr>()
+                    .map_err(|e| format!("Invalid RANSOMEYE_ORCHESTRATOR_STATUS_ADDR='{v}': {e}"))?,
+            ),
+            Err(_) => Some(DEFAULT_STATUS_ADDR.parse().expect("valid default status address")),
+        };
+
+        let token = match std::env::var("RANSOMEYE_SERVICE_HEARTBEAT_TOKEN_PATH") {
+            Ok(path) => Some(service_heartbeat::read_token(&path)?),
+            Err(_) => None,
+        };
+
+        let required: Vec<String> = match std::env::var("RANSOMEYE_REQUIRED_SERVICES") {
+            Ok(v) => v
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+
+        let dependencies = match std::env::var("RANSOMEYE_SERVICE_DEPENDENCIES") {
+            Ok(v) => super::service_graph::parse_dependencies(&v)
+                .map_err(|e| format!("Invalid RANSOMEYE_SERVICE_DEPENDENCIES: {e}"))?,
+            Err(_) => Vec::new(),
+        };
+
+        match listen_addr {
+            // Heartbeats from other hosts must be authenticated
+            Some(addr) if token.is_none() && !addr.ip().is_loopback() => {
+                return Err(format!(
+                    "FAIL-CLOSED: RANSOMEYE_ORCHESTRATOR_STATUS_ADDR={addr} is not loopback; set RANSOMEYE_SERVICE_HEARTBEAT_TOKEN_PATH"
+                ));
+            }
+            None if !required.is_empty() => {
+                return Err(format!(
+                    "FAIL-CLOSED: RANSOMEYE_REQUIRED_SERVICES={} needs the status listener (RANSOMEYE_ORCHESTRATOR_STATUS_ADDR is off)",
+                    required.join(",")
+                ));
+            }
+            _ => {}
+        }
+
+        Ok(Self {
+            listen_addr,
+            token,
+            required,
+            dependencies,
+            stale_after: Duration::from_secs(env_u64("RANSOMEYE_SERVICE_STALE_AFTER_SECS", 30)?),
+            registration_wait: Duration::from_secs(env_u64("RANSOMEYE_SERVICE_REGISTRATION_WAIT_SECS", 60)?),
+            check_interval: Duration::from_secs(env_u64("RANSOMEYE_SERVICE_LIVENESS_CHECK_SECS", 5)?),
+        })
+    }
+}
+
+/// Queued liveness transition, written to the audit log by the run loop.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "transition", rename_all = "snake_case")]
+pub enum LivenessChange {
+    Registered { service: String, instance_id: String, boot_id: Uuid },
+    Restarted { service: String, instance_id: String, previous_boot_id: Uuid, boot_id: Uuid },
+    Stale { service: String, instance_id: String, silent_secs: u64 },
+    Recovered { service: String, instance_id: String, boot_id: Uuid },
+}
+
+impl LivenessChange {
+    pub fn service(&self) -> &str {
+        match self {
+            LivenessChange::Registered { service, .. }
+            | LivenessChange::Restarted { service, .. }
+            | LivenessChange::Stale { service, .. }
+            | LivenessChange::Recovered { service, .. } => service,
+        }
+    }
+
+    pub fn instance_id(&self) -> &str {
+        match self {
+            LivenessChange::Registered { instance_id, .. }
+            | LivenessChange::Restarted { instance_id, .. }
+            | LivenessChange::Stale { instance_id, .. }
+            | LivenessChange::Recovered { instance_id, .. } => instance_id,
+        }
+    }
+
+    pub fn is_stale(&self) -> bool {
+        matches!(self, LivenessChange::Stale { .. })
+    }
+}
+
+/// One registered instance as reported by GET /v1/status.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ServiceLiveness {
+    pub service: String,
+    pub instance_id: String,
+    pub boot_id: Uuid,
+    pub version: Option<String>,
+    /// Build provenance reported by the service (commit, Cargo.lock and SBOM hashes)
+    pub build: Option<serde_json::Value>,
+    pub pid: u32,
+    pub status: ServiceStatus,
+    pub required: bool,
+    pub live: bool,
+    pub interval_secs: u64,
+    pub heartbeat_age_secs: u64,
+    pub last_heartbeat_at: DateTime<Utc>,
+    pub registered_at: DateTime<Utc>,
+    pub restarts: u32,
+    /// Last health report the instance sent (None: the service does not report one)
+    pub health: Option<HealthReport>,
+}
+
+#[derive(Debug)]
+struct ServiceEntry {
+    boot_id: Uuid,
+    version: Option<String>,
+    build: Option<serde_json::Value>,
+    pid: u32,
+    status: ServiceStatus,
+    interval_secs: u64,
+    registered_at: DateTime<Utc>,
+    last_seen: Instant,
+    last_seen_at: DateTime<Utc>,
+    restarts: u32,
+    health: Option<HealthReport>,
+    /// Reported stale and not seen since
+    stale: bool,
+    /// Heartbeat received since the last sweep
+    fresh: bool,
+}
+
+/// Result of one sweep: queued transitions plus instances that sent a heartbeat since the last one.
+#[derive(Debug, Default)]
+pub struct SweepOutcome {
+    pub changes: Vec<LivenessChange>,
+    pub heartbeats: Vec<ServiceLiveness>,
+}
+
+pub struct ServiceRegistry {
+    /// New for every orchestrator process start (returned in every acknowledgement)
+    instance: Uuid,
+    required: Vec<String>,
+    stale_after: Duration,
+    services: RwLock<BTreeMap<(String, String), ServiceEntry>>,
+    pending: Mutex<Vec<LivenessChange>>,
+}
+
+impl ServiceRegistry {
+    pub fn new(required: Vec<String>, stale_after: Duration) -> Self {
+        Self {
+            instance: Uuid::new_v4(),
+            required,
+            stale_after,
+            services: RwLock::new(BTreeMap::new()),
+            pending: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn instance(&self) -> Uuid {
+        self.instance
+    }
+
+    pub fn required(&self) -> &[String] {
+        &self.required
+    }
+
+    pub fn is_required(&self, service: &str) -> bool {
+        self.required.iter().any(|s| s == service)
+    }
+
+    /// Record a validated heartbeat received at `now`.
+    pub fn record(&self, hb: &Heartbeat, now: Instant, now_utc: DateTime<Utc>) {
+        let key = (hb.service.clone(), hb.instance_id.clone());
+        let mut services = self.services.write();
+        let change = match services.get_mut(&key) {
+            None => {
+                services.insert(
+                    key,
+                    ServiceEntry {
+                        boot_id: hb.boot_id,
+                        version: hb.version.clone(),
+                        build: hb.build.clone(),
+                        pid: hb.pid,
+                        status: hb.status,
+                        interval_secs: hb.interval_secs,
+                        registered_at: now_utc,
+                        last_seen: now,
+                        last_seen_at: now_utc,
+                        restarts: 0,
+                        health: hb.health.clone(),
+                        stale: false,
+                        fresh: true,
+                    },
+                );
+                Some(LivenessChange::Registered {
+                    service: hb.service.clone(),
+                    instance_id: hb.instance_id.clone(),
+                    boot_id: hb.boot_id,
+                })
+            }
+            // Restart handoff: the old process drains next to its successor and must not replace it
+            Some(entry) if entry.boot_id != hb.boot_id && hb.status == ServiceStatus::Draining => None,
+            Some(entry) => {
+                let change = if entry.boot_id != hb.boot_id {
+                    entry.restarts = entry.restarts.saturating_add(1);
+                    Some(LivenessChange::Restarted {
+                        service: hb.service.clone(),
+                        instance_id: hb.instance_id.clone(),
+                        previous_boot_id: entry.boot_id,
+                        boot_id: hb.boot_id,
+                    })
+                } else if entry.stale {
+                    Some(LivenessChange::Recovered {
+                        service: hb.service.clone(),
+                        instance_id: hb.instance_id.clone(),
+                        boot_id: hb.boot_id,
+                    })
+                } else {
+                    None
+                };
+                entry.boot_id = hb.boot_id;
+                entry.version = hb.version.clone();
+                entry.build = hb.build.clone();
+                entry.pid = hb.pid;
+                entry.status = hb.status;
+                entry.interval_secs = hb.interval_secs;
+                entry.health = hb.health.clone();
+                entry.last_seen = now;
+                entry.last_seen_at = now_utc;
+                entry.stale = false;
+                entry.fresh = true;
+                change
+            }
+        };
+        drop(services);
+        if let Some(change) = change {
+            self.pending.lock().push(change);
+        }
+    }
+
+    /// Mark instances silent for longer than `stale_after` and drain queued transitions.
+    pub fn sweep(&self, now: Instant) -> SweepOutcome {
+        let mut changes = std::mem::take(&mut *self.pending.lock());
+        let mut heartbeats = Vec::new();
+        let mut services = self.services.write();
+        for ((service, instance_id), entry) in services.iter_mut() {
+            let silent = now.saturating_duration_since(entry.last_seen);
+            if !entry.stale && silent > self.stale_after {
+                entry.stale = true;
+                changes.push(LivenessChange::Stale {
+                    service: service.clone(),
+                    instance_id: instance_id.clone(),
+                    silent_secs: silent.as_secs(),
+                });
+            }
+            if entry.fresh {
+                entry.fresh = false;
+                heartbeats.push(self.liveness(service, instance_id, entry, now));
+            }
+        }
+        SweepOutcome { changes, heartbeats }
+    }
+
+    fn liveness(&self, service: &str, instance_id: &str, entry: &ServiceEntry, now: Instant) -> ServiceLiveness {
+        let age = now.saturating_duration_since(entry.last_seen);
+        ServiceLiveness {
+            service: service.to_string(),
+            instance_id: instance_id.to_string(),
+            boot_id: entry.boot_id,
+            version: entry.version.clone(),
+            build: entry.build.clone(),
+            pid: entry.pid,
+            status: entry.status,
+            required: self.is_required(service),
+            live: age <= self.stale_after,
+            interval_secs: entry.interval_secs,
+            heartbeat_age_secs: age.as_secs(),
+            last_heartbeat_at: entry.last_seen_at,
+            registered_at: entry.registered_at,
+            restarts: entry.restarts,
+            health: entry.health.clone(),
+        }
+    }
+
+    pub fn snapshot(&self, now: Instant) -> Vec<ServiceLiveness> {
+        self.services
+            .read()
+            .iter()
+            .map(|((service, instance_id), entry)| self.liveness(service, instance_id, entry, now))
+            .collect()
+    }
+
+    /// Required services without a live instance that is serving.
+    pub fn missing_required(&self, now: Instant) -> Vec<String> {
+        let services = self.services.read();
+        self.required
+            .iter()
+            .filter(|name| {
+                !services.iter().any(|((service, _), entry)| {
+                    service == *name
+                        && entry.status.is_serving()
+                        && now.saturating_duration_since(entry.last_seen) <= self.stale_after
+                })
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Health of each required service: unhealthy without a live serving instance, healthy when one
+    /// of them is ready and reports healthy (or reports no health), degraded otherwise.
+    pub fn required_health(&self, now: Instant) -> Vec<(String, HealthStatus, Option<String>)> {
+        let services = self.services.read();
+        self.required
+            .iter()
+            .map(|name| {
+                let serving: Vec<&ServiceEntry> = services
+                    .iter()
+                    .filter(|((service, _), entry)| {
+                        service == name
+                            && entry.status.is_serving()
+                            && now.saturating_duration_since(entry.last_seen) <= self.stale_after
+                    })
+                    .map(|(_, entry)| entry)
+                    .collect();
+                if serving.is_empty() {
+                    return (name.clone(), HealthStatus::Unhealthy, Some("no live serving instance".to_string()));
+                }
+                let healthy = serving.iter().any(|e| {
+                    e.status == ServiceStatus::Ready
+                        && e.health.as_ref().map(|h| h.status == HealthStatus::Healthy).unwrap_or(true)
+                });
+                if healthy {
+                    (name.clone(), HealthStatus::Healthy, None)
+                } else {
+                    (name.clone(), HealthStatus::Degraded, Some(format!("{} serving instance(s), none healthy", serving.len())))
+                }
+            })
+            .collect()
+    }
+}
+
+/// GET /v1/status response.
+#[derive(Debug, Clone, Serialize)]
+pub struct OrchestratorStatus {
+    pub component: &'static str,
+    pub orchestrator_instance: Uuid,
+    pub state: &'static str,
+    /// Serving: health.status is healthy or degraded
+    pub healthy: bool,
+    pub config_drift_unhealthy: bool,
+    pub required_services: Vec<String>,
+    pub missing_required: Vec<String>,
+    pub services: Vec<ServiceLiveness>,
+    /// Latest database capacity forecast (None before the first sample or without Postgres)
+    pub capacity: Option<CapacityForecast>,
+    /// Subsystems: lifecycle, config_drift, db_capacity (once forecast), one `<check>_check` entry per
+    /// failing periodic check and one entry per required service
+    pub health: HealthReport,
+}
+
+/// Shared with the status listener.
+#[derive(Clone)]
+pub struct StatusState {
+    pub registry: Arc<ServiceRegistry>,
+    pub token: Option<Arc<String>>,
+    pub current_state: Arc<RwLock<OrchestratorState>>,
+    pub config_drift_unhealthy: Arc<AtomicBool>,
+    pub capacity: Arc<RwLock<Option<CapacityForecast>>>,
+    /// Periodic checks whose last run failed (check -> error), cleared when the check succeeds
+    pub failed_checks: Arc<RwLock<BTreeMap<&'static str, String>>>,
+}
+
+impl StatusState {
+    pub(crate) fn check_token(&self, headers: &HeaderMap) -> Result<(), StatusCode> {
+        let Some(expected) = &self.token else {
+            return Ok(());
+        };
+        let provided = headers
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .ok_or(StatusCode::UNAUTHORIZED)?;
+        let a = Sha256::digest(expected.as_bytes());
+        let b = Sha256::digest(provided.trim().as_bytes());
+        if crypto::constant_time_eq(&a, &b) {
+            Ok(())
+        } else {
+            warn!("Rejected status/heartbeat request with invalid token");
+            Err(StatusCode::UNAUTHORIZED)
+        }
+    }
+
+    pub fn status(&self, now: Instant) -> OrchestratorStatus {
+        let state = *self.current_state.read();
+        let drift = self.config_drift_unhealthy.load(Ordering::SeqCst);
+        let missing_required = self.registry.missing_required(now);
+
+        let mut health = HealthReport::new(COMPONENT, self.registry.instance().to_string());
+        if state == OrchestratorState::Running {
+            health.push_subsystem("lifecycle", HealthStatus::Healthy, None);
+        } else {
+            health.push_subsystem("lifecycle", HealthStatus::Unhealthy, Some(state.as_str().to_string()));
+        }
+        if drift {
+            health.push_subsystem("config_drift", HealthStatus::Unhealthy, Some("critical drift detected".to_string()));
+        } else {
+            health.push_subsystem("config_drift", HealthStatus::Healthy, None);
+        }
+        let capacity = self.capacity.read().clone();
+        match capacity.as_ref().map(|c| (c.level, c.days_until_full)) {
+            Some((CapacityLevel::Ok, _)) => health.push_subsystem("db_capacity", HealthStatus::Healthy, None),
+            Some((CapacityLevel::Low, days)) => health.push_subsystem(
+                "db_capacity",
+                HealthStatus::Degraded,
+                Some(format!("database full in {:.1} days", days.unwrap_or(0.0))),
+            ),
+            // Not enough history or no capacity configured: not a fault
+            Some((CapacityLevel::Unknown, _)) | None => {}
+        }
+        if let Some(days) = capacity.as_ref().and_then(|c| c.days_until_full) {
+            health.set_metric("db_days_until_full", days);
+        }
+        for (check, error) in self.failed_checks.read().iter() {
+            health.push_subsystem(format!("{check}_check"), HealthStatus::Degraded, Some(error.clone()));
+        }
+        for (service, status, detail) in self.registry.required_health(now) {
+            health.push_subsystem(service, status, detail);
+        }
+        health.set_metric("required_services_missing", missing_required.len() as f64);
+
+        let services = self.registry.snapshot(now);
+        health.set_metric("service_instances_live", services.iter().filter(|s| s.live).count() as f64);
+        OrchestratorStatus {
+            component: COMPONENT,
+            orchestrator_instance: self.registry.instance(),
+            state: state.as_str(),
+            healthy: health.status.is_serving(),
+            config_drift_unhealthy: drift,
+            required_services: self.registry.required().to_vec(),
+            missing_required,
+            services,
+            capacity,
+            health,
+        }
+    }
+}
+
+/// POST /v1/services/heartbeat
+async fn handle_heartbeat(
+    State(state): State<StatusState>,
+    headers: HeaderMap,
+    Json(hb): Json<Heartbeat>,
+) -> Result<Json<HeartbeatAck>, StatusCode> {
+    state.check_token(&headers)?;
+    if let Err(e) = hb.validate() {
+        warn!("Rejected heartbeat: {}", e);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let now = Instant::now();
+    state.registry.record(&hb, now, Utc::now());
+    let status = state.status(now);
+    Ok(Json(HeartbeatAck {
+        orchestrator_instance: status.orchestrator_instance,
+        orchestrator_state: status.state.to_string(),
+        orchestrator_healthy: status.healthy,
+        required: state.registry.is_required(&hb.service),
+        received_at: Utc::now(),
+    }))
+}
+
+/// GET /v1/status: 200 when healthy, 503 otherwise (same body).
+async fn handle_status(
+    State(state): State<StatusState>,
+    headers: HeaderMap,
+) -> Result<(StatusCode, Json<OrchestratorStatus>), StatusCode> {
+    state.check_token(&headers)?;
+    let status = state.status(Instant::now());
+    let code = if status.healthy { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    Ok((code, Json(status)))
+}
+
+pub fn router(state: StatusState) -> Router {
+    Router::new()
+        .route(service_heartbeat::HEARTBEAT_PATH, post(handle_heartbeat))
+        .route(service_heartbeat::STATUS_PATH, get(handle_status))
+        .with_state(state)
+}
+
+/// Bind the status listener (FAIL-CLOSED on bind error) and serve it in the background.
+pub async fn serve(addr: SocketAddr, state: StatusState) -> Result<tokio::task::JoinHandle<()>, String> {
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .map_err(|e| format!("Failed to bind status listener {addr}: {e}"))?;
+    info!("Orchestrator status listener on {} ({} and {})", addr, service_heartbeat::STATUS_PATH, service_heartbeat::HEARTBEAT_PATH);
+    let app = router(state);
+    Ok(tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, app).await {
+            error!("Orchestrator status listener stopped: {}", e);
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hb(service: &str, instance: &str, boot_id: Uuid, status: ServiceStatus) -> Heartbeat {
+        Heartbeat {
+            service: service.to_string(),
+            instance_id: instance.to_string(),
+            boot_id,
+            version: Some("1.0.0".to_string()),
+            pid: 42,
+            status,
+            interval_secs: 10,
+            sent_at: Utc::now(),
+            details: None,
+            build: None,
+            health: None,
+        }
+    }
+
+    fn registry() -> ServiceRegistry {
+        ServiceRegistry::new(vec!["ransomeye_ingestion".to_string()], Duration::from_secs(30))
+    }
+
+    #[test]
+    fn required_service_missing_until_ready() {
+        let reg = registry();
+        let t0 = Instant::now();
+        assert_eq!(reg.missing_required(t0), vec!["ransomeye_ingestion".to_string()]);
+
+        let boot = Uuid::new_v4();
+        reg.record(&hb("ransomeye_ingestion", "host-a", boot, ServiceStatus::Starting), t0, Utc::now());
+        assert_eq!(reg.missing_required(t0).len(), 1);
+
+        reg.record(&hb("ransomeye_ingestion", "host-a", boot, ServiceStatus::Ready), t0, Utc::now());
+        assert!(reg.missing_required(t0).is_empty());
+        assert_eq!(reg.missing_required(t0 + Duration::from_secs(31)).len(), 1);
+    }
+
+    #[test]
+    fn sweep_reports_registration_stale_and_recovery_once() {
+        let reg = registry();
+        let t0 = Instant::now();
+        let boot = Uuid::new_v4();
+        reg.record(&hb("ransomeye_ingestion", "host-a", boot, ServiceStatus::Ready), t0, Utc::now());
+
+        let out = reg.sweep(t0);
+        assert!(matches!(out.changes.as_slice(), [LivenessChange::Registered { .. }]));
+        assert_eq!(out.heartbeats.len(), 1);
+
+        let out = reg.sweep(t0 + Duration::from_secs(31));
+        assert!(matches!(out.changes.as_slice(), [LivenessChange::Stale { silent_secs: 31, .. }]));
+        assert!(out.heartbeats.is_empty());
+        assert!(reg.sweep(t0 + Duration::from_secs(40)).changes.is_empty());
+
+        let t1 = t0 + Duration::from_secs(45);
+        reg.record(&hb("ransomeye_ingestion", "host-a", boot, ServiceStatus::Ready), t1, Utc::now());
+        let out = reg.sweep(t1);
+        assert!(matches!(out.changes.as_slice(), [LivenessChange::Recovered { .. }]));
+    }
+
+    #[test]
+    fn new_boot_id_is_a_restart() {
+        let reg = registry();
+        let t0 = Instant::now();
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        reg.record(&hb("ransomeye_ingestion", "host-a", first, ServiceStatus::Ready), t0, Utc::now());
+        reg.record(&hb("ransomeye_ingestion", "host-a", second, ServiceStatus::Ready), t0, Utc::now());
+
+        let out = reg.sweep(t0);
+        assert_eq!(out.changes.len(), 2);
+        assert_eq!(
+            out.changes[1],
+            LivenessChange::Restarted {
+                service: "ransomeye_ingestion".to_string(),
+                instance_id: "host-a".to_string(),
+                previous_boot_id: first,
+                boot_id: second,
+            }
+        );
+        assert_eq!(reg.snapshot(t0)[0].restarts, 1);
+    }
+
+    #[test]
+    fn draining_predecessor_does_not_replace_successor() {
+        let reg = registry();
+        let t0 = Instant::now();
+        let (old, new) = (Uuid::new_v4(), Uuid::new_v4());
+        reg.record(&hb("ransomeye_ingestion", "host-a", old, ServiceStatus::Ready), t0, Utc::now());
+        reg.record(&hb("ransomeye_ingestion", "host-a", new, ServiceStatus::Ready), t0, Utc::now());
+        reg.record(&hb("ransomeye_ingestion", "host-a", old, ServiceStatus::Draining), t0, Utc::now());
+
+        let snapshot = reg.snapshot(t0);
+        assert_eq!(snapshot[0].boot_id, new);
+        assert_eq!(snapshot[0].status, ServiceStatus::Ready);
+        assert_eq!(reg.sweep(t0).changes.len(), 2);
+        assert!(reg.missing_required(t0).is_empty());
+    }
+
+    #[test]
+    fn required_service_health_follows_reported_health() {
+        let reg = registry();
+        let t0 = Instant::now();
+        let missing = reg.required_health(t0);
+        assert_eq!(missing[0].1, HealthStatus::Unhealthy);
+        assert_eq!(missing[0].2.as_deref(), Some("no live serving instance"));
+
+        let boot = Uuid::new_v4();
+        let mut beat = hb("ransomeye_ingestion", "host-a", boot, ServiceStatus::Ready);
+        reg.record(&beat, t0, Utc::now());
+        assert_eq!(reg.required_health(t0)[0].1, HealthStatus::Healthy);
+
+        beat.health = Some(
+            HealthReport::new("ransomeye_ingestion", "host-a").with_subsystem("orchestrator_link", HealthStatus::Degraded, None),
+        );
+        reg.record(&beat, t0, Utc::now());
+        assert_eq!(reg.required_health(t0)[0].1, HealthStatus::Degraded);
+        assert_eq!(reg.snapshot(t0)[0].health.as_ref().unwrap().status, HealthStatus::Degraded);
+
+        // A second, healthy instance makes the service healthy again
+        let mut other = hb("ransomeye_ingestion", "host-b", Uuid::new_v4(), ServiceStatus::Ready);
+        other.health = Some(HealthReport::new("ransomeye_ingestion", "host-b"));
+        reg.record(&other, t0, Utc::now());
+        assert_eq!(reg.required_health(t0)[0].1, HealthStatus::Healthy);
+        assert_eq!(reg.required_health(t0 + Duration::from_secs(31))[0].1, HealthStatus::Unhealthy);
+    }
+}
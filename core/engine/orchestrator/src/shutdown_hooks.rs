@@ -0,0 +1,236 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/orchestrator/src/shutdown_hooks.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Graceful shutdown hooks - flush/teardown callbacks registered per subsystem, awaited by the orchestrator in reverse startup order under a per-hook timeout
+
+/*
+ * Shutdown Hooks
+ *
+ * A component that buffers state (the database writer, the policy audit log) registers a hook
+ * under the subsystem that started it. On shutdown the orchestrator walks the dependency graph in
+ * reverse startup order and, for each subsystem, awaits its hooks newest first before stopping
+ * the subsystem itself. Every hook runs in its own task under a timeout (its own, or
+ * RANSOMEYE_SHUTDOWN_HOOK_TIMEOUT_SECS): a hook that fails or overruns is reported and
+ * shutdown moves on to the next one, so one stuck component cannot keep the others from flushing.
+ */
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+/// Upper bound for RANSOMEYE_SHUTDOWN_HOOK_TIMEOUT_SECS (supervisors kill long before this)
+pub const MAX_HOOK_TIMEOUT_SECS: u64 = 300;
+
+pub type HookFuture<'a> = Pin<Box<dyn Future<Output = Result<(), String>> + Send + 'a>>;
+
+/// Flush/teardown callback awaited when its subsystem stops.
+pub trait ShutdownHook: Send + Sync {
+    /// Name in logs, component_health and immutable_audit_log
+    fn name(&self) -> &str;
+
+    fn run(&self) -> HookFuture<'_>;
+
+    /// Overrides the configured per-hook timeout
+    fn timeout(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Hook built from a closure (see `hook`).
+pub struct FnHook<F> {
+    name: String,
+    timeout: Option<Duration>,
+    f: F,
+}
+
+impl<F> FnHook<F> {
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+impl<F> ShutdownHook for FnHook<F>
+where
+    F: Fn() -> HookFuture<'static> + Send + Sync,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn run(&self) -> HookFuture<'_> {
+        (self.f)()
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+}
+
+pub fn hook<F>(name: &str, f: F) -> FnHook<F>
+where
+    F: Fn() -> HookFuture<'static> + Send + Sync,
+{
+    FnHook { name: name.to_string(), timeout: None, f }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShutdownConfig {
+    /// Time each hook gets unless it sets its own (RANSOMEYE_SHUTDOWN_HOOK_TIMEOUT_SECS)
+    pub hook_timeout: Duration,
+}
+
+impl ShutdownConfig {
+    pub fn from_env() -> Result<Self, String> {
+        let secs = match std::env::var("RANSOMEYE_SHUTDOWN_HOOK_TIMEOUT_SECS") {
+            Ok(v) => v
+                .parse::<u64>()
+                .ok()
+                .filter(|s| (1..=MAX_HOOK_TIMEOUT_SECS).contains(s))
+                .ok_or_else(|| {
+                    format!("Invalid RANSOMEYE_SHUTDOWN_HOOK_TIMEOUT_SECS='{}' (1-{})", v, MAX_HOOK_TIMEOUT_SECS)
+                })?,
+            Err(_) => 10,
+        };
+        Ok(Self { hook_timeout: Duration::from_secs(secs) })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "outcome", content = "error", rename_all = "snake_case")]
+pub enum HookOutcome {
+    Completed,
+    Failed(String),
+    TimedOut,
+}
+
+impl HookOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookOutcome::Completed => "completed",
+            HookOutcome::Failed(_) => "failed",
+            HookOutcome::TimedOut => "timed_out",
+        }
+    }
+
+    pub fn is_completed(&self) -> bool {
+        *self == HookOutcome::Completed
+    }
+}
+
+/// Result of one hook, as recorded in component_health and immutable_audit_log.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HookResult {
+    pub subsystem: String,
+    pub hook: String,
+    #[serde(flatten)]
+    pub outcome: HookOutcome,
+    pub elapsed_ms: u64,
+    pub timeout_ms: u64,
+}
+
+/// Hooks by subsystem, in registration order.
+#[derive(Default)]
+pub struct ShutdownHooks {
+    hooks: Vec<(String, Arc<dyn ShutdownHook>)>,
+}
+
+impl ShutdownHooks {
+    pub fn register(&mut self, subsystem: &str, hook: Arc<dyn ShutdownHook>) {
+        self.hooks.push((subsystem.to_string(), hook));
+    }
+
+    /// Hooks of one subsystem, newest first (the reverse of the order they were registered in).
+    pub fn for_subsystem(&self, subsystem: &str) -> Vec<Arc<dyn ShutdownHook>> {
+        self.hooks
+            .iter()
+            .rev()
+            .filter(|(s, _)| s == subsystem)
+            .map(|(_, h)| h.clone())
+            .collect()
+    }
+}
+
+/// Await one hook in its own task; on timeout the task is aborted.
+pub async fn run_hook(subsystem: &str, hook: Arc<dyn ShutdownHook>, default_timeout: Duration) -> HookResult {
+    let timeout = hook.timeout().unwrap_or(default_timeout);
+    let name = hook.name().to_string();
+    let started = Instant::now();
+    let mut task = tokio::spawn(async move { hook.run().await });
+    let outcome = match tokio::time::timeout(timeout, &mut task).await {
+        Ok(Ok(Ok(()))) => HookOutcome::Completed,
+        Ok(Ok(Err(e))) => HookOutcome::Failed(e),
+        Ok(Err(e)) => HookOutcome::Failed(format!("hook task ended abnormally: {}", e)),
+        Err(_) => {
+            task.abort();
+            HookOutcome::TimedOut
+        }
+    };
+    HookResult {
+        subsystem: subsystem.to_string(),
+        hook: name,
+        outcome,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+        timeout_ms: timeout.as_millis() as u64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ok(name: &str, log: Arc<parking_lot::Mutex<Vec<String>>>) -> Arc<dyn ShutdownHook> {
+        let name_owned = name.to_string();
+        Arc::new(hook(name, move || {
+            let log = log.clone();
+            let name = name_owned.clone();
+            Box::pin(async move {
+                log.lock().push(name);
+                Ok(())
+            })
+        }))
+    }
+
+    #[test]
+    fn test_hooks_run_newest_first_per_subsystem() {
+        let log = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let mut hooks = ShutdownHooks::default();
+        hooks.register("storage", ok("db_writer", log.clone()));
+        hooks.register("policy", ok("policy_audit_log", log.clone()));
+        hooks.register("storage", ok("spool", log.clone()));
+        let names = |s: &str| hooks.for_subsystem(s).iter().map(|h| h.name().to_string()).collect::<Vec<_>>();
+        assert_eq!(names("storage"), vec!["spool", "db_writer"]);
+        assert_eq!(names("policy"), vec!["policy_audit_log"]);
+        assert!(names("bus").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failures_and_timeouts_are_reported() {
+        let log = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let done = run_hook("storage", ok("db_writer", log.clone()), Duration::from_secs(1)).await;
+        assert_eq!(done.outcome, HookOutcome::Completed);
+        assert_eq!(*log.lock(), vec!["db_writer".to_string()]);
+
+        let failing: Arc<dyn ShutdownHook> = Arc::new(hook("spool", || Box::pin(async { Err("disk full".to_string()) })));
+        let failed = run_hook("storage", failing, Duration::from_secs(1)).await;
+        assert_eq!(failed.outcome, HookOutcome::Failed("disk full".to_string()));
+        assert_eq!(serde_json::to_value(&failed).unwrap()["error"], "disk full");
+
+        // The hook's own timeout wins over the configured one
+        let stuck: Arc<dyn ShutdownHook> = Arc::new(
+            hook("stuck", || {
+                Box::pin(async {
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                    Ok(())
+                })
+            })
+            .with_timeout(Duration::from_millis(50)),
+        );
+        let timed_out = run_hook("policy", stuck, Duration::from_secs(30)).await;
+        assert_eq!(timed_out.outcome, HookOutcome::TimedOut);
+        assert_eq!(timed_out.timeout_ms, 50);
+        assert!(timed_out.elapsed_ms < 30_000);
+    }
+}
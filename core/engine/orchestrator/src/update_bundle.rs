@@ -0,0 +1,615 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/orchestrator/src/update_bundle.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Offline (air-gapped) update bundles - signed manifest of binaries, policy and rule packs and schema migrations, verification of signature and every artifact hash, staging of the update and the audited apply record.
+
+/*
+ * Offline Update Bundles
+ *
+ * Air-gapped sites receive updates as a bundle directory (on removable media):
+ *
+ *   manifest.json          bundle manifest, signed as exact bytes
+ *   manifest.sig           {"signer_key": "<hex Ed25519 public key>", "signature": "<hex signature>"}
+ *   bin/...                binaries
+ *   policies/...           policy documents
+ *   rules/...              rule packs
+ *   migrations/...sql      schema migrations, applied in `sequence` order
+ *
+ * The manifest lists every artifact with its kind, relative path, size and SHA-256. A bundle is
+ * accepted only if a key in RANSOMEYE_UPDATE_SIGNERS signed the manifest, every artifact matches
+ * its size and hash, and the directory holds nothing else. Bundle versions only go up: a version
+ * not above every applied bundle is refused.
+ *
+ * Applying a bundle copies the artifacts into <staging>/<version>/ and checks the copies again
+ * (the media may change while it is read). The migrations then run in one transaction with the
+ * update_bundles row; if any fails, nothing of the bundle is kept. <staging>/current is switched
+ * to the new version only after the commit, for the installer to activate the binaries, policies
+ * and rule packs. The apply is written to the audit log (UPDATE_BUNDLE_APPLIED).
+ */
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Read;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use crypto::signature::{verify_ed25519, ED25519_PUBLIC_KEY_LEN};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::info;
+use uuid::Uuid;
+
+use super::db::CoreDb;
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+pub const SIGNATURE_FILE: &str = "manifest.sig";
+/// Manifest format this build understands
+pub const BUNDLE_FORMAT: u32 = 1;
+pub const DEFAULT_STAGING_DIR: &str = "/var/lib/ransomeye/updates";
+/// Link in the staging directory to the bundle the installer activates
+pub const CURRENT_LINK: &str = "current";
+const MAX_ARTIFACTS: usize = 1024;
+const MAX_MANIFEST_BYTES: u64 = 4 * 1024 * 1024;
+const MAX_FIELD_BYTES: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtifactKind {
+    Binary,
+    Policy,
+    RulePack,
+    Migration,
+}
+
+impl ArtifactKind {
+    /// Directory of the bundle the artifacts of this kind live in
+    pub fn dir(&self) -> &'static str {
+        match self {
+            ArtifactKind::Binary => "bin",
+            ArtifactKind::Policy => "policies",
+            ArtifactKind::RulePack => "rules",
+            ArtifactKind::Migration => "migrations",
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ArtifactKind::Binary => "binary",
+            ArtifactKind::Policy => "policy",
+            ArtifactKind::RulePack => "rule_pack",
+            ArtifactKind::Migration => "migration",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BundleArtifact {
+    pub kind: ArtifactKind,
+    /// Relative path inside the bundle, under the kind's directory
+    pub path: String,
+    pub size: u64,
+    /// Lowercase hex SHA-256
+    pub sha256: String,
+    /// Order of a migration (migrations only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BundleManifest {
+    pub format: u32,
+    /// Monotonic across bundles
+    pub version: i64,
+    /// Release label, e.g. 2026.10.1
+    pub release: String,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub artifacts: Vec<BundleArtifact>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BundleSignature {
+    pub signer_key: String,
+    pub signature: String,
+}
+
+/// Keys trusted to sign update bundles (RANSOMEYE_UPDATE_SIGNERS).
+#[derive(Debug, Clone, Default)]
+pub struct UpdateSigners {
+    keys: Vec<[u8; ED25519_PUBLIC_KEY_LEN]>,
+}
+
+impl UpdateSigners {
+    pub fn from_env() -> Result<Self, String> {
+        let signers = Self::parse(&std::env::var("RANSOMEYE_UPDATE_SIGNERS").unwrap_or_default())
+            .map_err(|e| format!("RANSOMEYE_UPDATE_SIGNERS: {e}"))?;
+        if signers.keys.is_empty() {
+            return Err("RANSOMEYE_UPDATE_SIGNERS is not set; no bundle can be verified".to_string());
+        }
+        Ok(signers)
+    }
+
+    /// Comma-separated hex Ed25519 public keys.
+    pub fn parse(list: &str) -> Result<Self, String> {
+        let keys = list
+            .split(',')
+            .map(str::trim)
+            .filter(|e| !e.is_empty())
+            .map(|entry| {
+                hex::decode(entry)
+                    .ok()
+                    .and_then(|b| b.try_into().ok())
+                    .ok_or_else(|| format!("'{entry}' is not a hex Ed25519 public key"))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { keys })
+    }
+}
+
+/// Bundle whose signature and artifacts verified
+#[derive(Debug, Clone)]
+pub struct VerifiedBundle {
+    pub dir: PathBuf,
+    pub manifest: BundleManifest,
+    /// Hex SHA-256 of manifest.json
+    pub manifest_sha256: String,
+    pub signer_key: String,
+}
+
+impl VerifiedBundle {
+    /// Migrations in the order they are applied
+    pub fn migrations(&self) -> Vec<&BundleArtifact> {
+        let mut migrations: Vec<_> = self.manifest.artifacts.iter().filter(|a| a.kind == ArtifactKind::Migration).collect();
+        migrations.sort_by_key(|a| a.sequence);
+        migrations
+    }
+}
+
+fn check_text(field: &str, value: &str) -> Result<(), String> {
+    if value.trim().is_empty() {
+        return Err(format!("{field} is empty"));
+    }
+    if value.len() > MAX_FIELD_BYTES {
+        return Err(format!("{field} exceeds {MAX_FIELD_BYTES} bytes"));
+    }
+    Ok(())
+}
+
+fn check_path(artifact: &BundleArtifact) -> Result<(), String> {
+    let path = &artifact.path;
+    let mut parts = path.split('/');
+    if parts.next() != Some(artifact.kind.dir()) {
+        return Err(format!("{} '{}' is not under {}/", artifact.kind.as_str(), path, artifact.kind.dir()));
+    }
+    let rest: Vec<&str> = parts.collect();
+    if rest.is_empty() || rest.iter().any(|p| p.is_empty() || *p == "." || *p == ".." || p.contains('\\') || p.contains('\0')) {
+        return Err(format!("artifact path '{path}' is not a plain relative path"));
+    }
+    Ok(())
+}
+
+impl BundleManifest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.format != BUNDLE_FORMAT {
+            return Err(format!("manifest format {} is not supported (expected {BUNDLE_FORMAT})", self.format));
+        }
+        if self.version < 1 {
+            return Err(format!("version {} is not positive", self.version));
+        }
+        check_text("release", &self.release)?;
+        check_text("created_by", &self.created_by)?;
+        if self.artifacts.is_empty() || self.artifacts.len() > MAX_ARTIFACTS {
+            return Err(format!("a bundle holds 1 to {MAX_ARTIFACTS} artifacts"));
+        }
+        let mut paths = BTreeSet::new();
+        let mut sequences = BTreeSet::new();
+        for artifact in &self.artifacts {
+            check_path(artifact)?;
+            if !paths.insert(artifact.path.as_str()) {
+                return Err(format!("artifact '{}' is listed twice", artifact.path));
+            }
+            if artifact.sha256.len() != 64 || !artifact.sha256.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+                return Err(format!("artifact '{}' has no lowercase hex SHA-256", artifact.path));
+            }
+            match (artifact.kind, artifact.sequence) {
+                (ArtifactKind::Migration, Some(seq)) => {
+                    if !artifact.path.ends_with(".sql") {
+                        return Err(format!("migration '{}' is not a .sql file", artifact.path));
+                    }
+                    if !sequences.insert(seq) {
+                        return Err(format!("migration sequence {seq} is used twice"));
+                    }
+                }
+                (ArtifactKind::Migration, None) => return Err(format!("migration '{}' has no sequence", artifact.path)),
+                (_, Some(_)) => return Err(format!("only migrations carry a sequence ('{}')", artifact.path)),
+                (_, None) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+fn sha256_file(path: &Path) -> Result<(u64, String), String> {
+    let mut file = std::fs::File::open(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1024 * 1024];
+    let mut size = 0u64;
+    loop {
+        let n = file.read(&mut buf).map_err(|e| format!("{}: {e}", path.display()))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        size += n as u64;
+    }
+    Ok((size, hex::encode(hasher.finalize())))
+}
+
+/// Every file below `dir`, relative to it ('/'-separated). Symlinks and other non-regular files are refused.
+fn bundle_files(dir: &Path) -> Result<BTreeSet<String>, String> {
+    let mut files = BTreeSet::new();
+    let mut pending = vec![(dir.to_path_buf(), String::new())];
+    while let Some((path, rel)) = pending.pop() {
+        let entries = std::fs::read_dir(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+        for entry in entries {
+            let entry = entry.map_err(|e| format!("{}: {e}", path.display()))?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let rel = if rel.is_empty() { name } else { format!("{rel}/{name}") };
+            let file_type = entry.file_type().map_err(|e| format!("{rel}: {e}"))?;
+            if file_type.is_dir() {
+                pending.push((entry.path(), rel));
+            } else if file_type.is_file() {
+                files.insert(rel);
+            } else {
+                return Err(format!("'{rel}' is not a regular file"));
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Check the manifest signature against `signers`, the manifest itself, and every artifact's
+/// size and hash. FAIL-CLOSED: anything unlisted in the bundle directory refuses the bundle.
+pub fn verify_bundle(dir: &Path, signers: &UpdateSigners) -> Result<VerifiedBundle, String> {
+    let manifest_path = dir.join(MANIFEST_FILE);
+    let manifest_len = std::fs::metadata(&manifest_path).map_err(|e| format!("{}: {e}", manifest_path.display()))?.len();
+    if manifest_len > MAX_MANIFEST_BYTES {
+        return Err(format!("{MANIFEST_FILE} exceeds {MAX_MANIFEST_BYTES} bytes"));
+    }
+    let manifest_bytes = std::fs::read(&manifest_path).map_err(|e| format!("{}: {e}", manifest_path.display()))?;
+    let sig_path = dir.join(SIGNATURE_FILE);
+    let sig: BundleSignature = std::fs::read(&sig_path)
+        .map_err(|e| format!("{}: {e}", sig_path.display()))
+        .and_then(|b| serde_json::from_slice(&b).map_err(|e| format!("invalid {SIGNATURE_FILE}: {e}")))?;
+
+    let signer_key = hex::decode(sig.signer_key.trim()).map_err(|_| "signer key is not hex".to_string())?;
+    if !signers.keys.iter().any(|k| k.as_slice() == signer_key.as_slice()) {
+        return Err(format!("signer {} is not a trusted update signer", sig.signer_key));
+    }
+    let signature = hex::decode(sig.signature.trim()).map_err(|_| "signature is not hex".to_string())?;
+    verify_ed25519(&signer_key, &manifest_bytes, &signature).map_err(|_| "manifest signature does not verify".to_string())?;
+
+    let manifest: BundleManifest =
+        serde_json::from_slice(&manifest_bytes).map_err(|e| format!("invalid {MANIFEST_FILE}: {e}"))?;
+    manifest.validate()?;
+
+    let mut unlisted = bundle_files(dir)?;
+    unlisted.remove(MANIFEST_FILE);
+    unlisted.remove(SIGNATURE_FILE);
+    for artifact in &manifest.artifacts {
+        if !unlisted.remove(&artifact.path) {
+            return Err(format!("artifact '{}' is missing from the bundle", artifact.path));
+        }
+        let (size, sha256) = sha256_file(&dir.join(&artifact.path))?;
+        if size != artifact.size || sha256 != artifact.sha256 {
+            return Err(format!("artifact '{}' does not match the manifest (size {size}, sha256 {sha256})", artifact.path));
+        }
+    }
+    if let Some(extra) = unlisted.first() {
+        return Err(format!("'{extra}' is in the bundle but not in the manifest"));
+    }
+
+    Ok(VerifiedBundle {
+        dir: dir.to_path_buf(),
+        manifest_sha256: hex::encode(Sha256::digest(&manifest_bytes)),
+        signer_key: hex::encode(signer_key),
+        manifest,
+    })
+}
+
+/// Copy the bundle into `<staging_root>/<version>/` and check the copies against the manifest.
+/// A partial copy is never left under the final name.
+pub fn stage(bundle: &VerifiedBundle, staging_root: &Path) -> Result<PathBuf, String> {
+    let version = bundle.manifest.version;
+    let dest = staging_root.join(version.to_string());
+    if dest.exists() {
+        return Err(format!("{} already exists; bundle {version} was staged before", dest.display()));
+    }
+    let partial = staging_root.join(format!(".{version}.partial"));
+    if partial.exists() {
+        std::fs::remove_dir_all(&partial).map_err(|e| format!("{}: {e}", partial.display()))?;
+    }
+    let copy = || -> Result<(), String> {
+        let io = |p: &Path, e: std::io::Error| format!("{}: {e}", p.display());
+        for name in [MANIFEST_FILE, SIGNATURE_FILE] {
+            std::fs::create_dir_all(&partial).map_err(|e| io(&partial, e))?;
+            std::fs::copy(bundle.dir.join(name), partial.join(name)).map_err(|e| io(&partial.join(name), e))?;
+        }
+        for artifact in &bundle.manifest.artifacts {
+            let target = partial.join(&artifact.path);
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| io(parent, e))?;
+            }
+            std::fs::copy(bundle.dir.join(&artifact.path), &target).map_err(|e| io(&target, e))?;
+            let (size, sha256) = sha256_file(&target)?;
+            if size != artifact.size || sha256 != artifact.sha256 {
+                return Err(format!("staged copy of '{}' does not match the manifest", artifact.path));
+            }
+            let mode = if artifact.kind == ArtifactKind::Binary { 0o755 } else { 0o644 };
+            std::fs::set_permissions(&target, std::fs::Permissions::from_mode(mode)).map_err(|e| io(&target, e))?;
+        }
+        std::fs::rename(&partial, &dest).map_err(|e| io(&dest, e))
+    };
+    copy().inspect_err(|_| {
+        let _ = std::fs::remove_dir_all(&partial);
+    })?;
+    Ok(dest)
+}
+
+/// Point `<staging_root>/current` at the staged version (atomic rename of a fresh link).
+pub fn activate(staging_root: &Path, version: i64) -> Result<(), String> {
+    let link = staging_root.join(CURRENT_LINK);
+    let tmp = staging_root.join(format!(".{CURRENT_LINK}.{version}"));
+    let _ = std::fs::remove_file(&tmp);
+    std::os::unix::fs::symlink(version.to_string(), &tmp).map_err(|e| format!("{}: {e}", tmp.display()))?;
+    std::fs::rename(&tmp, &link).map_err(|e| format!("{}: {e}", link.display()))
+}
+
+/// Outcome of a bundle apply
+#[derive(Debug, Clone, Serialize)]
+pub struct AppliedBundle {
+    pub bundle_id: Uuid,
+    pub version: i64,
+    pub release: String,
+    pub staged_path: String,
+    pub migrations_applied: usize,
+    /// Artifact count per kind
+    pub artifacts: BTreeMap<&'static str, usize>,
+}
+
+/// Highest bundle version applied to this deployment
+pub async fn applied_version(db: &CoreDb) -> Result<Option<i64>, String> {
+    db.client()
+        .query_one("SELECT max(version) FROM update_bundles", &[])
+        .await
+        .map(|row| row.get(0))
+        .map_err(|e| format!("Failed to read applied bundle version: {e}"))
+}
+
+/// Stage a verified bundle, run its migrations and record it (one transaction), switch the
+/// current link and write the audit log. A failure before the commit leaves nothing behind.
+pub async fn apply(db: &CoreDb, bundle: &VerifiedBundle, staging_root: &Path, applied_by: &str) -> Result<AppliedBundle, String> {
+    check_text("applied_by", applied_by)?;
+    let manifest = &bundle.manifest;
+    if let Some(applied) = applied_version(db).await? {
+        if manifest.version <= applied {
+            return Err(format!("bundle version {} is not above the applied version {applied}", manifest.version));
+        }
+    }
+    std::fs::create_dir_all(staging_root).map_err(|e| format!("{}: {e}", staging_root.display()))?;
+    let staged = stage(bundle, staging_root)?;
+
+    let bundle_id = Uuid::new_v4();
+    let migrations = bundle.migrations();
+    let client = db.client();
+    let result = async {
+        client.batch_execute("BEGIN").await.map_err(|e| format!("Failed to begin bundle transaction: {e}"))?;
+        for migration in &migrations {
+            // Run the staged copy: it was checked against the manifest after the copy
+            let sql = std::fs::read_to_string(staged.join(&migration.path))
+                .map_err(|e| format!("migration '{}': {e}", migration.path))?;
+            client
+                .batch_execute(&sql)
+                .await
+                .map_err(|e| format!("migration '{}' (sequence {:?}) failed: {e}", migration.path, migration.sequence))?;
+            info!("[UPDATE-BUNDLE] migration applied: {} (sequence {:?})", migration.path, migration.sequence);
+        }
+        let manifest_json = serde_json::to_value(manifest).map_err(|e| format!("Failed to serialize manifest: {e}"))?;
+        client
+            .execute(
+                r#"
+                INSERT INTO update_bundles (bundle_id, version, release, manifest, manifest_sha256, signer_key, migrations_applied, staged_path, applied_by)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                "#,
+                &[
+                    &bundle_id,
+                    &manifest.version,
+                    &manifest.release,
+                    &manifest_json,
+                    &bundle.manifest_sha256,
+                    &bundle.signer_key,
+                    &(migrations.len() as i32),
+                    &staged.to_string_lossy().as_ref(),
+                    &applied_by,
+                ],
+            )
+            .await
+            .map_err(|e| format!("Failed to record bundle {} (applied concurrently?): {e}", manifest.version))?;
+        client.batch_execute("COMMIT").await.map_err(|e| format!("Failed to commit bundle transaction: {e}"))
+    }
+    .await;
+    if let Err(e) = result {
+        let _ = client.batch_execute("ROLLBACK").await;
+        let _ = std::fs::remove_dir_all(&staged);
+        return Err(e);
+    }
+
+    activate(staging_root, manifest.version)?;
+    let mut artifacts: BTreeMap<&'static str, usize> = BTreeMap::new();
+    for artifact in &manifest.artifacts {
+        *artifacts.entry(artifact.kind.as_str()).or_default() += 1;
+    }
+    db.insert_immutable_audit_log(
+        None,
+        "UPDATE_BUNDLE_APPLIED",
+        "other",
+        Some(bundle_id),
+        &serde_json::json!({
+            "bundle_id": bundle_id,
+            "version": manifest.version,
+            "release": manifest.release,
+            "manifest_sha256": bundle.manifest_sha256,
+            "signer_key": bundle.signer_key,
+            "created_by": manifest.created_by,
+            "applied_by": applied_by,
+            "artifacts": manifest.artifacts.iter().map(|a| serde_json::json!({
+                "kind": a.kind.as_str(),
+                "path": a.path,
+                "sha256": a.sha256,
+            })).collect::<Vec<_>>(),
+            "migrations_applied": migrations.len(),
+            "staged_path": staged.to_string_lossy(),
+        }),
+    )
+    .await?;
+    Ok(AppliedBundle {
+        bundle_id,
+        version: manifest.version,
+        release: manifest.release.clone(),
+        staged_path: staged.to_string_lossy().into_owned(),
+        migrations_applied: migrations.len(),
+        artifacts,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crypto::signature::Ed25519KeyPair;
+
+    struct Fixture {
+        dir: PathBuf,
+        key: Ed25519KeyPair,
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn write(dir: &Path, rel: &str, content: &[u8]) -> BundleArtifact {
+        let path = dir.join(rel);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, content).unwrap();
+        let kind = match rel.split('/').next().unwrap() {
+            "bin" => ArtifactKind::Binary,
+            "policies" => ArtifactKind::Policy,
+            "rules" => ArtifactKind::RulePack,
+            _ => ArtifactKind::Migration,
+        };
+        BundleArtifact {
+            kind,
+            path: rel.to_string(),
+            size: content.len() as u64,
+            sha256: hex::encode(Sha256::digest(content)),
+            sequence: None,
+        }
+    }
+
+    fn sign(fx: &Fixture, manifest: &BundleManifest) {
+        let bytes = serde_json::to_vec_pretty(manifest).unwrap();
+        std::fs::write(fx.dir.join(MANIFEST_FILE), &bytes).unwrap();
+        let sig = BundleSignature { signer_key: hex::encode(fx.key.public_key()), signature: hex::encode(fx.key.sign(&bytes)) };
+        std::fs::write(fx.dir.join(SIGNATURE_FILE), serde_json::to_vec(&sig).unwrap()).unwrap();
+    }
+
+    fn bundle() -> (Fixture, BundleManifest, UpdateSigners) {
+        let dir = std::env::temp_dir().join(format!("ransomeye-bundle-{}", Uuid::new_v4()));
+        let fx = Fixture { dir, key: Ed25519KeyPair::generate().unwrap() };
+        let mut migration = write(&fx.dir, "migrations/0001_add_column.sql", b"SELECT 1;");
+        migration.sequence = Some(1);
+        let manifest = BundleManifest {
+            format: BUNDLE_FORMAT,
+            version: 12,
+            release: "2026.10.1".to_string(),
+            created_by: "release-eng".to_string(),
+            created_at: Utc::now(),
+            description: None,
+            artifacts: vec![
+                write(&fx.dir, "bin/ransomeye_orchestrator", b"\x7fELF orchestrator"),
+                write(&fx.dir, "rules/ransomware.json", b"{\"rules\":[]}"),
+                migration,
+            ],
+        };
+        sign(&fx, &manifest);
+        let signers = UpdateSigners::parse(&hex::encode(fx.key.public_key())).unwrap();
+        (fx, manifest, signers)
+    }
+
+    #[test]
+    fn test_bundle_verifies_signature_hashes_and_layout() {
+        let (fx, manifest, signers) = bundle();
+        let verified = verify_bundle(&fx.dir, &signers).unwrap();
+        assert_eq!(verified.manifest, manifest);
+        assert_eq!(verified.migrations().len(), 1);
+
+        // Untrusted signer
+        let stranger = UpdateSigners::parse(&hex::encode(Ed25519KeyPair::generate().unwrap().public_key())).unwrap();
+        assert!(verify_bundle(&fx.dir, &stranger).is_err());
+
+        // Artifact changed after signing
+        std::fs::write(fx.dir.join("rules/ransomware.json"), b"{\"rules\":[1]}").unwrap();
+        assert!(verify_bundle(&fx.dir, &signers).unwrap_err().contains("does not match"));
+        std::fs::write(fx.dir.join("rules/ransomware.json"), b"{\"rules\":[]}").unwrap();
+
+        // File smuggled in next to the listed ones
+        std::fs::write(fx.dir.join("bin/extra"), b"payload").unwrap();
+        assert!(verify_bundle(&fx.dir, &signers).unwrap_err().contains("not in the manifest"));
+        std::fs::remove_file(fx.dir.join("bin/extra")).unwrap();
+
+        // Manifest edited after signing
+        let mut edited = std::fs::read_to_string(fx.dir.join(MANIFEST_FILE)).unwrap();
+        edited = edited.replace("\"version\": 12", "\"version\": 13");
+        std::fs::write(fx.dir.join(MANIFEST_FILE), edited).unwrap();
+        assert!(verify_bundle(&fx.dir, &signers).unwrap_err().contains("signature"));
+    }
+
+    #[test]
+    fn test_manifest_rejects_unsafe_paths_and_sequences() {
+        let (_fx, manifest, _) = bundle();
+        let with = |edit: &dyn Fn(&mut BundleManifest)| {
+            let mut m = manifest.clone();
+            edit(&mut m);
+            m.validate()
+        };
+        assert!(with(&|_| {}).is_ok());
+        assert!(with(&|m| m.artifacts[0].path = "bin/../manifest.json".to_string()).is_err());
+        assert!(with(&|m| m.artifacts[0].path = "/usr/bin/ransomeye".to_string()).is_err());
+        assert!(with(&|m| m.artifacts[1].path = "bin/ransomware.json".to_string()).is_err());
+        assert!(with(&|m| m.artifacts[2].sequence = None).is_err());
+        assert!(with(&|m| m.artifacts[0].sequence = Some(2)).is_err());
+        assert!(with(&|m| m.artifacts[0].sha256 = m.artifacts[0].sha256.to_uppercase()).is_err());
+        assert!(with(&|m| m.format = 2).is_err());
+    }
+
+    #[test]
+    fn test_stage_copies_and_activates() {
+        let (fx, _, signers) = bundle();
+        let verified = verify_bundle(&fx.dir, &signers).unwrap();
+        let staging = fx.dir.join("staging");
+        std::fs::create_dir_all(&staging).unwrap();
+        let staged = stage(&verified, &staging).unwrap();
+        assert_eq!(staged, staging.join("12"));
+        assert_eq!(std::fs::read(staged.join("bin/ransomeye_orchestrator")).unwrap(), b"\x7fELF orchestrator");
+        // The staged copy is itself a complete bundle
+        assert!(verify_bundle(&staged, &signers).is_ok());
+        assert!(stage(&verified, &staging).is_err());
+        activate(&staging, 12).unwrap();
+        assert_eq!(std::fs::read_link(staging.join(CURRENT_LINK)).unwrap(), PathBuf::from("12"));
+    }
+}
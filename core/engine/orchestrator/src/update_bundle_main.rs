@@ -0,0 +1,143 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/orchestrator/src/update_bundle_main.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Standalone offline update bundle binary - verifies a signed air-gapped bundle, and applies it (stage artifacts, run schema migrations, record the bundle version in update_bundles and the audit log).
+
+use std::path::{Path, PathBuf};
+use std::process;
+
+use tracing::{error, info};
+
+#[path = "lib.rs"]
+mod orchestrator;
+
+use orchestrator::db::{CoreDb, DbConfig};
+use orchestrator::update_bundle::{self, UpdateSigners, VerifiedBundle};
+
+fn usage_and_exit() -> ! {
+    eprintln!("RansomEye Offline Update Bundle");
+    eprintln!("");
+    eprintln!("USAGE:");
+    eprintln!("  ransomeye_update_bundle verify-bundle <bundle-dir>");
+    eprintln!("  ransomeye_update_bundle apply-bundle <bundle-dir> --by <operator> [--staging-dir <dir>]");
+    eprintln!("  ransomeye_update_bundle status");
+    eprintln!("");
+    eprintln!("NOTES:");
+    eprintln!("  - the bundle directory holds manifest.json, manifest.sig and the listed artifacts only");
+    eprintln!("  - RANSOMEYE_UPDATE_SIGNERS (comma-separated hex Ed25519 keys) must hold the manifest signer");
+    eprintln!("  - verify-bundle checks the signature and every artifact hash without touching the database");
+    eprintln!("  - apply-bundle stages into <staging-dir>/<version> (default {}, or RANSOMEYE_UPDATE_STAGING_DIR),", update_bundle::DEFAULT_STAGING_DIR);
+    eprintln!("    runs the migrations in one transaction and switches <staging-dir>/current");
+    eprintln!("  - a bundle version not above the applied version is refused");
+    eprintln!("  - DB env vars are required for apply-bundle and status: DB_HOST, DB_PORT, DB_NAME, DB_USER, DB_PASS");
+    process::exit(2);
+}
+
+fn arg_value(name: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter()
+        .position(|a| a == name)
+        .and_then(|i| args.get(i + 1).cloned())
+}
+
+async fn connect() -> CoreDb {
+    let cfg = match DbConfig::from_env_strict() {
+        Ok(c) => c,
+        Err(e) => {
+            error!("{e}");
+            process::exit(1);
+        }
+    };
+    match CoreDb::connect_strict(&cfg).await {
+        Ok(db) => db,
+        Err(e) => {
+            error!("FAIL-CLOSED: DB connect failed: {e}");
+            process::exit(1);
+        }
+    }
+}
+
+fn verified_bundle() -> VerifiedBundle {
+    let Some(dir) = std::env::args().nth(2).filter(|d| !d.starts_with("--")) else {
+        usage_and_exit();
+    };
+    let signers = match UpdateSigners::from_env() {
+        Ok(s) => s,
+        Err(e) => {
+            error!("FAIL-CLOSED: {e}");
+            process::exit(1);
+        }
+    };
+    match update_bundle::verify_bundle(Path::new(&dir), &signers) {
+        Ok(bundle) => bundle,
+        Err(e) => {
+            error!("FAIL-CLOSED: bundle {} refused: {}", dir, e);
+            process::exit(1);
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    orchestrator::BUILD_INFO.handle_version_flag("ransomeye_update_bundle");
+    tracing_subscriber::fmt().with_writer(std::io::stderr).init();
+
+    if std::env::args().any(|a| a == "--help" || a == "-h") {
+        usage_and_exit();
+    }
+    match std::env::args().nth(1).as_deref() {
+        Some("verify-bundle") => verify(),
+        Some("apply-bundle") => apply().await,
+        Some("status") => status().await,
+        _ => usage_and_exit(),
+    }
+}
+
+fn verify() {
+    let bundle = verified_bundle();
+    let m = &bundle.manifest;
+    info!(
+        "[UPDATE-BUNDLE] verified version={} release={} artifacts={} migrations={} signer={} manifest_sha256={}",
+        m.version,
+        m.release,
+        m.artifacts.len(),
+        bundle.migrations().len(),
+        bundle.signer_key,
+        bundle.manifest_sha256
+    );
+    for a in &m.artifacts {
+        info!("[UPDATE-BUNDLE]   {} {} size={} sha256={}", a.kind.as_str(), a.path, a.size, a.sha256);
+    }
+}
+
+async fn apply() {
+    let Some(by) = arg_value("--by") else {
+        usage_and_exit();
+    };
+    let bundle = verified_bundle();
+    let staging = arg_value("--staging-dir")
+        .or_else(|| std::env::var("RANSOMEYE_UPDATE_STAGING_DIR").ok())
+        .unwrap_or_else(|| update_bundle::DEFAULT_STAGING_DIR.to_string());
+    let db = connect().await;
+    match update_bundle::apply(&db, &bundle, &PathBuf::from(&staging), &by).await {
+        Ok(applied) => info!(
+            "[UPDATE-BUNDLE] applied bundle_id={} version={} release={} migrations={} artifacts={:?} staged={}",
+            applied.bundle_id, applied.version, applied.release, applied.migrations_applied, applied.artifacts, applied.staged_path
+        ),
+        Err(e) => {
+            error!("FAIL-CLOSED: {e}");
+            process::exit(1);
+        }
+    }
+}
+
+async fn status() {
+    let db = connect().await;
+    match update_bundle::applied_version(&db).await {
+        Ok(Some(version)) => info!("[UPDATE-BUNDLE] applied version={}", version),
+        Ok(None) => info!("[UPDATE-BUNDLE] no bundle applied"),
+        Err(e) => {
+            error!("FAIL-CLOSED: {e}");
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,381 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/orchestrator/src/usage_stats.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Opt-in anonymized usage statistics - aggregate event rates, agent counts, feature usage and version of a deployment, coarsened and written to a local file the operator may submit; nothing is ever transmitted
+
+/*
+ * Usage Statistics
+ *
+ * Collection is off unless RANSOMEYE_USAGE_STATS_ENABLED=true. The report is a local file; this
+ * module has no network client other than the database connection, and submitting the file is
+ * left to the operator.
+ *
+ * Anonymization, applied by aggregate() to everything loaded from the database:
+ *
+ *   aggregate only   every query is a fixed count(*), optionally grouped by an enum column;
+ *                    no identifier, name, address, path or free text is ever selected
+ *   closed vocabulary categories (source types, agent types, features, flags) come from the
+ *                    lists below; anything else is folded into "other" (flags are dropped)
+ *   coarsened        counts below 10 are reported as "<10", larger ones rounded to two
+ *                    significant digits (12345 -> 12000)
+ *   per day          event and detection counts are daily averages over the period
+ *   unlinkable       no deployment id, hostname or tenant name; the period end is a date
+ */
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Serialize, Serializer};
+
+use super::db::CoreDb;
+
+pub const FORMAT: &str = "ransomeye-usage-stats";
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Counts below this are reported as "<10"
+pub const MIN_REPORTED_COUNT: u64 = 10;
+
+pub const SOURCE_TYPES: &[&str] = &[
+    "linux_agent",
+    "windows_agent",
+    "dpi_probe",
+    "core_engine",
+    "ai_core",
+    "alert_engine",
+    "policy_engine",
+    "correlation_engine",
+    "llm",
+    "response_engine",
+    "forensic_engine",
+    "unknown",
+];
+
+pub const AGENT_TYPES: &[&str] = &["linux_agent", "windows_agent", "dpi_probe", "unknown"];
+
+/// Runtime feature flags known to ingest (see docs/FEATURE_FLAGS.md)
+pub const FEATURE_FLAGS: &[&str] = &["memory_acquisition", "pcap_capture", "sandbox_detonation", "yara_scanning"];
+
+/// Features and the rows that show use in the period ($1 = period start)
+pub const FEATURES: &[(&str, &str)] = &[
+    ("pcap_capture", "SELECT count(*) FROM pcap_captures WHERE requested_at >= $1"),
+    ("memory_acquisition", "SELECT count(*) FROM memory_acquisitions WHERE requested_at >= $1"),
+    ("sandbox_detonation", "SELECT count(*) FROM sandbox_submissions WHERE queued_at >= $1"),
+    ("yara_scanning", "SELECT count(*) FROM yara_scan_requests WHERE requested_at >= $1"),
+    (
+        "detection_suppression",
+        "SELECT count(*) FROM detection_suppressions WHERE expires_at >= $1 AND (expired_at IS NULL OR expired_at >= $1)",
+    ),
+    (
+        "maintenance_windows",
+        "SELECT count(*) FROM maintenance_windows WHERE ends_at >= $1 AND (cancelled_at IS NULL OR cancelled_at >= $1)",
+    ),
+    ("webhooks", "SELECT count(*) FROM webhook_subscriptions WHERE enabled OR disabled_at >= $1"),
+    ("config_rollout", "SELECT count(*) FROM agent_config_rollouts WHERE started_at >= $1 OR finished_at IS NULL"),
+    ("legal_holds", "SELECT count(*) FROM legal_holds WHERE released_at IS NULL OR released_at >= $1"),
+    ("annotations", "SELECT count(*) FROM annotations WHERE created_at >= $1"),
+    ("agent_log_shipping", "SELECT count(*) FROM agent_log_segments WHERE received_at >= $1"),
+    ("update_bundles", "SELECT count(*) FROM update_bundles WHERE applied_at >= $1"),
+];
+
+/// A coarsened count
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Count {
+    /// Rounded to two significant digits
+    Rounded(u64),
+    /// At least one, fewer than MIN_REPORTED_COUNT
+    Few,
+}
+
+impl Count {
+    pub fn of(n: u64) -> Self {
+        if n == 0 {
+            return Count::Rounded(0);
+        }
+        if n < MIN_REPORTED_COUNT {
+            return Count::Few;
+        }
+        let digits = n.ilog10() + 1;
+        if digits <= 2 {
+            return Count::Rounded(n);
+        }
+        let unit = 10u64.pow(digits - 2);
+        Count::Rounded(n.saturating_add(unit / 2) / unit * unit)
+    }
+
+    /// Daily average of `total` over `days`; any use at all is at least Few
+    pub fn per_day(total: u64, days: i64) -> Self {
+        match Self::of(total / days.max(1) as u64) {
+            Count::Rounded(0) if total > 0 => Count::Few,
+            c => c,
+        }
+    }
+}
+
+impl Serialize for Count {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Count::Rounded(n) => serializer.serialize_u64(*n),
+            Count::Few => serializer.serialize_str("<10"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UsageStatsConfig {
+    /// RANSOMEYE_USAGE_STATS_ENABLED (default false)
+    pub enabled: bool,
+    pub period: Duration,
+}
+
+impl UsageStatsConfig {
+    pub fn from_env() -> Self {
+        let enabled = std::env::var("RANSOMEYE_USAGE_STATS_ENABLED")
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+        Self { enabled, period: Duration::days(7) }
+    }
+}
+
+/// Raw counts from the database (never written anywhere as they are)
+#[derive(Debug, Clone, Default)]
+pub struct UsageInputs {
+    pub events_by_source: BTreeMap<String, u64>,
+    pub detections: u64,
+    /// Active, not decommissioned agents
+    pub agents_by_type: BTreeMap<String, u64>,
+    pub tenants: u64,
+    /// Keyed by FEATURES name
+    pub feature_rows: BTreeMap<String, u64>,
+    /// Flag keys with a global row that is off
+    pub flags_disabled: Vec<String>,
+    /// Tenant override rows per flag key
+    pub flag_overrides: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FeatureUsage {
+    pub used: bool,
+    pub rows: Count,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FlagUsage {
+    pub disabled_globally: bool,
+    pub tenant_overrides: Count,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageStats {
+    pub format: &'static str,
+    pub format_version: u32,
+    pub version: String,
+    pub os: &'static str,
+    pub arch: &'static str,
+    pub period_end: NaiveDate,
+    pub period_days: i64,
+    pub events_per_day: BTreeMap<String, Count>,
+    pub detections_per_day: Count,
+    pub agents: BTreeMap<String, Count>,
+    pub tenants: Count,
+    pub features: BTreeMap<String, FeatureUsage>,
+    pub feature_flags: BTreeMap<String, FlagUsage>,
+}
+
+/// Sum `counts` into the `allowed` categories, everything else into "other".
+fn fold(counts: &BTreeMap<String, u64>, allowed: &[&str]) -> BTreeMap<String, u64> {
+    let mut folded = BTreeMap::new();
+    for (category, n) in counts {
+        let key = if allowed.contains(&category.as_str()) { category.as_str() } else { "other" };
+        *folded.entry(key.to_string()).or_insert(0) += n;
+    }
+    folded
+}
+
+/// The anonymized statistics of `inputs` (pure; inputs come from load_inputs).
+pub fn aggregate(inputs: &UsageInputs, period: Duration, now: DateTime<Utc>, version: &str) -> UsageStats {
+    let days = period.num_days().max(1);
+    let feature_flags = FEATURE_FLAGS
+        .iter()
+        .map(|flag| {
+            let usage = FlagUsage {
+                disabled_globally: inputs.flags_disabled.iter().any(|f| f == flag),
+                tenant_overrides: Count::of(inputs.flag_overrides.get(*flag).copied().unwrap_or(0)),
+            };
+            (flag.to_string(), usage)
+        })
+        .collect();
+    UsageStats {
+        format: FORMAT,
+        format_version: FORMAT_VERSION,
+        version: version.to_string(),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        period_end: now.date_naive(),
+        period_days: days,
+        events_per_day: fold(&inputs.events_by_source, SOURCE_TYPES)
+            .into_iter()
+            .map(|(source, n)| (source, Count::per_day(n, days)))
+            .collect(),
+        detections_per_day: Count::per_day(inputs.detections, days),
+        agents: fold(&inputs.agents_by_type, AGENT_TYPES).into_iter().map(|(t, n)| (t, Count::of(n))).collect(),
+        tenants: Count::of(inputs.tenants),
+        features: FEATURES
+            .iter()
+            .map(|(feature, _)| {
+                let rows = inputs.feature_rows.get(*feature).copied().unwrap_or(0);
+                (feature.to_string(), FeatureUsage { used: rows > 0, rows: Count::of(rows) })
+            })
+            .collect(),
+        feature_flags,
+    }
+}
+
+async fn count(db: &CoreDb, sql: &str, since: DateTime<Utc>, what: &str) -> Result<u64, String> {
+    let row = db
+        .client()
+        .query_one(sql, &[&since])
+        .await
+        .map_err(|e| format!("FAIL-CLOSED: Cannot count {}: {e}", what))?;
+    Ok(row.get::<_, i64>(0).max(0) as u64)
+}
+
+async fn grouped(db: &CoreDb, sql: &str, what: &str) -> Result<BTreeMap<String, u64>, String> {
+    let rows = db
+        .client()
+        .query(sql, &[])
+        .await
+        .map_err(|e| format!("FAIL-CLOSED: Cannot count {}: {e}", what))?;
+    Ok(rows.iter().map(|r| (r.get::<_, String>(0), r.get::<_, i64>(1).max(0) as u64)).collect())
+}
+
+/// Counts since `since`.
+pub async fn load_inputs(db: &CoreDb, since: DateTime<Utc>) -> Result<UsageInputs, String> {
+    let events_by_source = db
+        .client()
+        .query("SELECT source_type::text, count(*) FROM raw_events WHERE received_at >= $1 GROUP BY 1", &[&since])
+        .await
+        .map_err(|e| format!("FAIL-CLOSED: Cannot count raw events: {e}"))?
+        .iter()
+        .map(|r| (r.get::<_, String>(0), r.get::<_, i64>(1).max(0) as u64))
+        .collect();
+    let detections = count(db, "SELECT count(*) FROM detection_results WHERE created_at >= $1", since, "detections").await?;
+    let agents_by_type = grouped(
+        db,
+        "SELECT agent_type::text, count(*) FROM agents WHERE is_active AND decommissioned_at IS NULL GROUP BY 1",
+        "agents",
+    )
+    .await?;
+    let tenants = db
+        .client()
+        .query_one("SELECT count(DISTINCT tenant_id) FROM agents WHERE is_active AND decommissioned_at IS NULL", &[])
+        .await
+        .map_err(|e| format!("FAIL-CLOSED: Cannot count tenants: {e}"))?
+        .get::<_, i64>(0)
+        .max(0) as u64;
+
+    let mut feature_rows = BTreeMap::new();
+    for (feature, sql) in FEATURES {
+        feature_rows.insert(feature.to_string(), count(db, sql, since, feature).await?);
+    }
+
+    // Only flag keys are read, and aggregate() keeps the known ones
+    let flags_disabled = db
+        .client()
+        .query("SELECT flag_key FROM feature_flags WHERE tenant_id IS NULL AND NOT enabled", &[])
+        .await
+        .map_err(|e| format!("FAIL-CLOSED: Cannot read feature flags: {e}"))?
+        .iter()
+        .map(|r| r.get::<_, String>(0))
+        .collect();
+    let flag_overrides = grouped(
+        db,
+        "SELECT flag_key, count(*) FROM feature_flags WHERE tenant_id IS NOT NULL GROUP BY 1",
+        "feature flag overrides",
+    )
+    .await?;
+
+    Ok(UsageInputs { events_by_source, detections, agents_by_type, tenants, feature_rows, flags_disabled, flag_overrides })
+}
+
+/// Write `stats` to `<dir>/usage-stats-<period_end>.json` (replacing a report of the same day).
+pub fn write_report(dir: &Path, stats: &UsageStats) -> Result<PathBuf, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {e}", dir.display()))?;
+    let path = dir.join(format!("usage-stats-{}.json", stats.period_end));
+    let partial = path.with_extension("json.partial");
+    let json = serde_json::to_string_pretty(stats).map_err(|e| format!("Failed to serialize usage statistics: {e}"))?;
+    fs::write(&partial, json + "\n").map_err(|e| format!("Cannot write {}: {e}", partial.display()))?;
+    fs::rename(&partial, &path).map_err(|e| format!("Cannot write {}: {e}", path.display()))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts(entries: &[(&str, u64)]) -> BTreeMap<String, u64> {
+        entries.iter().map(|(k, n)| (k.to_string(), *n)).collect()
+    }
+
+    #[test]
+    fn counts_are_coarsened() {
+        assert_eq!(Count::of(0), Count::Rounded(0));
+        assert_eq!(Count::of(1), Count::Few);
+        assert_eq!(Count::of(9), Count::Few);
+        assert_eq!(Count::of(10), Count::Rounded(10));
+        assert_eq!(Count::of(99), Count::Rounded(99));
+        assert_eq!(Count::of(12345), Count::Rounded(12000));
+        assert_eq!(Count::of(995), Count::Rounded(1000));
+        assert_eq!(Count::of(u64::MAX), Count::Rounded(18_000_000_000_000_000_000));
+        assert_eq!(Count::per_day(700_000, 7), Count::Rounded(100_000));
+        // One event a week is still reported as used
+        assert_eq!(Count::per_day(1, 7), Count::Few);
+        assert_eq!(serde_json::to_string(&[Count::Few, Count::Rounded(120)]).unwrap(), r#"["<10",120]"#);
+    }
+
+    #[test]
+    fn only_known_categories_are_reported() {
+        let inputs = UsageInputs {
+            events_by_source: counts(&[("linux_agent", 7_000_000), ("dpi_probe", 35), ("acme_custom_source", 70)]),
+            detections: 14,
+            agents_by_type: counts(&[("linux_agent", 412), ("windows_agent", 3)]),
+            tenants: 2,
+            feature_rows: counts(&[("pcap_capture", 130), ("not_a_feature", 5)]),
+            flags_disabled: vec!["sandbox_detonation".to_string(), "customer_project_x".to_string()],
+            flag_overrides: counts(&[("pcap_capture", 1), ("customer_project_x", 40)]),
+        };
+        let stats = aggregate(&inputs, Duration::days(7), Utc::now(), "1.2.3");
+
+        assert_eq!(stats.events_per_day["linux_agent"], Count::Rounded(1_000_000));
+        assert_eq!(stats.events_per_day["dpi_probe"], Count::Few);
+        assert_eq!(stats.events_per_day["other"], Count::Rounded(10));
+        assert_eq!(stats.detections_per_day, Count::Few);
+        assert_eq!(stats.agents["linux_agent"], Count::Rounded(410));
+        assert_eq!(stats.agents["windows_agent"], Count::Few);
+        assert_eq!(stats.tenants, Count::Few);
+        assert_eq!(stats.features.len(), FEATURES.len());
+        assert!(stats.features["pcap_capture"].used);
+        assert!(!stats.features["memory_acquisition"].used);
+        assert_eq!(stats.feature_flags.keys().map(String::as_str).collect::<Vec<_>>(), FEATURE_FLAGS.to_vec());
+        assert!(stats.feature_flags["sandbox_detonation"].disabled_globally);
+        assert_eq!(stats.feature_flags["pcap_capture"].tenant_overrides, Count::Few);
+
+        let json = serde_json::to_string(&stats).unwrap();
+        for name in ["acme_custom_source", "not_a_feature", "customer_project_x"] {
+            assert!(!json.contains(name), "{name} leaked into {json}");
+        }
+    }
+
+    #[test]
+    fn report_is_written_per_day() {
+        let dir = std::env::temp_dir().join(format!("ransomeye-usage-stats-{}", uuid::Uuid::new_v4()));
+        let stats = aggregate(&UsageInputs::default(), Duration::days(7), "2026-03-02T10:00:00Z".parse().unwrap(), "1.2.3");
+        let path = write_report(&dir, &stats).unwrap();
+        assert_eq!(path, dir.join("usage-stats-2026-03-02.json"));
+        let written: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written["format"], FORMAT);
+        assert_eq!(written["events_per_day"], serde_json::json!({}));
+        assert_eq!(written["tenants"], 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,99 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/orchestrator/src/usage_stats_main.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Standalone usage statistics binary - when opted in (RANSOMEYE_USAGE_STATS_ENABLED=true), computes anonymized aggregate deployment statistics and writes them to a local JSON file for the operator to review and optionally submit; never transmits anything.
+
+use std::path::PathBuf;
+use std::process;
+
+use chrono::{Duration, Utc};
+use tracing::{error, info};
+
+#[path = "lib.rs"]
+mod orchestrator;
+
+use orchestrator::db::{CoreDb, DbConfig};
+use orchestrator::usage_stats::{self, UsageStatsConfig};
+
+const DEFAULT_OUTPUT_DIR: &str = "/var/lib/ransomeye/usage-stats";
+
+fn usage_and_exit() -> ! {
+    eprintln!("RansomEye Usage Statistics");
+    eprintln!("");
+    eprintln!("USAGE:");
+    eprintln!("  ransomeye_usage_stats [--output-dir <dir>] [--period-days <n>]");
+    eprintln!("");
+    eprintln!("NOTES:");
+    eprintln!("  - Opt-in: does nothing unless RANSOMEYE_USAGE_STATS_ENABLED=true.");
+    eprintln!("  - Writes <dir>/usage-stats-<date>.json (default dir {}); nothing is sent anywhere.", DEFAULT_OUTPUT_DIR);
+    eprintln!("  - Only coarsened aggregate counts are written: no ids, names, addresses or tenants.");
+    eprintln!("  - DB env vars are required: DB_HOST, DB_PORT, DB_NAME, DB_USER, DB_PASS");
+    process::exit(2);
+}
+
+fn arg_value(name: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter()
+        .position(|a| a == name)
+        .and_then(|i| args.get(i + 1).cloned())
+}
+
+#[tokio::main]
+async fn main() {
+    orchestrator::BUILD_INFO.handle_version_flag("ransomeye_usage_stats");
+    tracing_subscriber::fmt().with_writer(std::io::stderr).init();
+
+    if std::env::args().any(|a| a == "--help" || a == "-h") {
+        usage_and_exit();
+    }
+
+    let mut cfg = UsageStatsConfig::from_env();
+    if let Some(v) = arg_value("--period-days") {
+        match v.parse::<i64>() {
+            Ok(n) if (1..=90).contains(&n) => cfg.period = Duration::days(n),
+            _ => usage_and_exit(),
+        }
+    }
+    let output_dir = PathBuf::from(arg_value("--output-dir").unwrap_or_else(|| DEFAULT_OUTPUT_DIR.to_string()));
+
+    if !cfg.enabled {
+        info!("Usage statistics are opt-in (RANSOMEYE_USAGE_STATS_ENABLED=true); nothing collected");
+        process::exit(0);
+    }
+
+    let db_cfg = match DbConfig::from_env_strict() {
+        Ok(c) => c,
+        Err(e) => {
+            error!("{e}");
+            process::exit(1);
+        }
+    };
+
+    let db = match CoreDb::connect_strict(&db_cfg).await {
+        Ok(db) => db,
+        Err(e) => {
+            error!("FAIL-CLOSED: DB connect failed: {e}");
+            process::exit(1);
+        }
+    };
+
+    let now = Utc::now();
+    let inputs = match usage_stats::load_inputs(&db, now - cfg.period).await {
+        Ok(i) => i,
+        Err(e) => {
+            error!("{e}");
+            process::exit(1);
+        }
+    };
+
+    let stats = usage_stats::aggregate(&inputs, cfg.period, now, orchestrator::BUILD_INFO.version);
+    match usage_stats::write_report(&output_dir, &stats) {
+        Ok(path) => {
+            info!("Usage statistics written (not transmitted) | path={}", path.display());
+            println!("{}", path.display());
+        }
+        Err(e) => {
+            error!("{e}");
+            process::exit(1);
+        }
+    }
+}
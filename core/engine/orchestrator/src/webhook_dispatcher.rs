@@ -0,0 +1,319 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/orchestrator/src/webhook_dispatcher.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Webhook delivery worker - claims due webhook_deliveries rows, POSTs signed JSON to subscribers, schedules retries with backoff and dead-letters exhausted deliveries
+
+use std::time::Duration;
+
+use serde_json::Value as JsonValue;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use ingest::webhooks::{self, RetryPolicy};
+
+use super::db::{CoreDb, DbConfig};
+
+const MAX_ERROR_LEN: usize = 512;
+
+#[derive(Debug, Clone)]
+pub struct WebhookDispatcherConfig {
+    pub enabled: bool,
+    pub poll_interval: Duration,
+    pub request_timeout: Duration,
+    pub batch_size: i64,
+    pub retry: RetryPolicy,
+}
+
+fn env_u64(key: &str, default_value: u64) -> Result<u64, String> {
+    match std::env::var(key) {
+        Ok(v) => v
+            .parse::<u64>()
+            .ok()
+            .filter(|n| *n > 0)
+            .ok_or_else(|| format!("Invalid {}='{}' (expected integer > 0)", key, v)),
+        Err(_) => Ok(default_value),
+    }
+}
+
+impl WebhookDispatcherConfig {
+    pub fn from_env() -> Result<Self, String> {
+        let enabled = std::env::var("RANSOMEYE_WEBHOOKS_ENABLED")
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(true);
+        let max_attempts = env_u64("RANSOMEYE_WEBHOOK_MAX_ATTEMPTS", 8)?;
+        Ok(Self {
+            enabled,
+            poll_interval: Duration::from_secs(env_u64("RANSOMEYE_WEBHOOK_POLL_SECS", 5)?),
+            request_timeout: Duration::from_secs(env_u64("RANSOMEYE_WEBHOOK_TIMEOUT_SECS", 10)?),
+            batch_size: env_u64("RANSOMEYE_WEBHOOK_BATCH_SIZE", 50)? as i64,
+            retry: RetryPolicy {
+                max_attempts: u32::try_from(max_attempts)
+                    .map_err(|_| format!("Invalid RANSOMEYE_WEBHOOK_MAX_ATTEMPTS='{}'", max_attempts))?,
+                ..RetryPolicy::default()
+            },
+        })
+    }
+
+    /// How long a claimed row stays invisible to other claims; a crashed attempt becomes due again after it.
+    fn lease(&self) -> Duration {
+        self.request_timeout + Duration::from_secs(30)
+    }
+}
+
+/// What to record after one attempt.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttemptOutcome {
+    Delivered { status_code: u16 },
+    Retry { after: Duration, status_code: Option<u16>, error: String },
+    Dead { status_code: Option<u16>, error: String },
+}
+
+/// Classify one attempt: 2xx delivers, anything else (including redirects) retries until the
+/// policy is exhausted. `attempts` counts failures before this attempt.
+pub fn classify(attempts: u32, result: Result<u16, String>, policy: &RetryPolicy) -> AttemptOutcome {
+    let (status_code, error) = match result {
+        Ok(code) if (200..300).contains(&code) => return AttemptOutcome::Delivered { status_code: code },
+        Ok(code) => (Some(code), format!("HTTP {}", code)),
+        Err(e) => (None, e),
+    };
+    let mut error = error;
+    if error.len() > MAX_ERROR_LEN {
+        let mut cut = MAX_ERROR_LEN;
+        while !error.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        error.truncate(cut);
+    }
+    match policy.next_delay(attempts + 1) {
+        Some(after) => AttemptOutcome::Retry { after, status_code, error },
+        None => AttemptOutcome::Dead { status_code, error },
+    }
+}
+
+struct ClaimedDelivery {
+    delivery_id: Uuid,
+    event_type: String,
+    payload: JsonValue,
+    attempts: i32,
+    url: String,
+    secret: Vec<u8>,
+}
+
+pub struct WebhookDispatcher {
+    cfg: WebhookDispatcherConfig,
+    db: CoreDb,
+    http: reqwest::Client,
+}
+
+impl WebhookDispatcher {
+    /// Own connection: the orchestrator's shared CoreDb runs BEGIN/COMMIT batches that must not interleave.
+    pub async fn connect(cfg: WebhookDispatcherConfig, db_cfg: &DbConfig) -> Result<Self, String> {
+        let db = CoreDb::connect_strict(db_cfg).await?;
+        let http = reqwest::Client::builder()
+            .timeout(cfg.request_timeout)
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .map_err(|e| format!("Failed to build webhook HTTP client: {e}"))?;
+        Ok(Self { cfg, db, http })
+    }
+
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            info!(
+                "Webhook dispatcher running (poll={}s, max_attempts={})",
+                self.cfg.poll_interval.as_secs(),
+                self.cfg.retry.max_attempts
+            );
+            let mut tick = tokio::time::interval(self.cfg.poll_interval);
+            loop {
+                tick.tick().await;
+                // Drain everything due before sleeping again
+                loop {
+                    match self.run_once().await {
+                        Ok(n) if n as i64 >= self.cfg.batch_size => continue,
+                        Ok(_) => break,
+                        Err(e) => {
+                            error!("Webhook dispatch pass failed: {}", e);
+                            break;
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    /// One claim-and-deliver pass; returns deliveries attempted.
+    pub async fn run_once(&self) -> Result<usize, String> {
+        let claimed = self.claim().await?;
+        for delivery in &claimed {
+            let result = self.post(delivery).await;
+            let outcome = classify(delivery.attempts.max(0) as u32, result, &self.cfg.retry);
+            self.record(delivery, &outcome).await?;
+        }
+        Ok(claimed.len())
+    }
+
+    async fn claim(&self) -> Result<Vec<ClaimedDelivery>, String> {
+        let rows = self
+            .db
+            .client()
+            .query(
+                r#"
+                UPDATE webhook_deliveries d
+                SET next_attempt_at = now() + make_interval(secs => $2)
+                FROM webhook_subscriptions s
+                WHERE s.webhook_id = d.webhook_id
+                  AND d.delivery_id IN (
+                    SELECT delivery_id FROM webhook_deliveries
+                    WHERE status = 'pending' AND next_attempt_at <= now()
+                    ORDER BY next_attempt_at
+                    LIMIT $1
+                    FOR UPDATE SKIP LOCKED
+                  )
+                RETURNING d.delivery_id, d.event_type, d.payload, d.attempts, s.url, s.secret
+                "#,
+                &[&self.cfg.batch_size, &self.cfg.lease().as_secs_f64()],
+            )
+            .await
+            .map_err(|e| format!("Failed to claim webhook deliveries: {e}"))?;
+        Ok(rows
+            .iter()
+            .map(|r| ClaimedDelivery {
+                delivery_id: r.get(0),
+                event_type: r.get(1),
+                payload: r.get(2),
+                attempts: r.get(3),
+                url: r.get(4),
+                secret: r.get(5),
+            })
+            .collect())
+    }
+
+    async fn post(&self, delivery: &ClaimedDelivery) -> Result<u16, String> {
+        let body = serde_json::to_vec(&delivery.payload).map_err(|e| format!("payload serialization: {e}"))?;
+        let signature = webhooks::sign(&delivery.secret, chrono::Utc::now().timestamp(), &body);
+        let response = self
+            .http
+            .post(&delivery.url)
+            .header("Content-Type", "application/json")
+            .header(webhooks::EVENT_HEADER, &delivery.event_type)
+            .header(webhooks::DELIVERY_HEADER, delivery.delivery_id.to_string())
+            .header(webhooks::SIGNATURE_HEADER, signature)
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| format!("transport error: {e}"))?;
+        Ok(response.status().as_u16())
+    }
+
+    async fn record(&self, delivery: &ClaimedDelivery, outcome: &AttemptOutcome) -> Result<(), String> {
+        let client = self.db.client();
+        let result = match outcome {
+            AttemptOutcome::Delivered { status_code } => {
+                client
+                    .execute(
+                        r#"
+                        UPDATE webhook_deliveries
+                        SET status = 'delivered', attempts = attempts + 1, delivered_at = now(),
+                            last_status_code = $2, last_error = NULL
+                        WHERE delivery_id = $1
+                        "#,
+                        &[&delivery.delivery_id, &(*status_code as i32)],
+                    )
+                    .await
+            }
+            AttemptOutcome::Retry { after, status_code, error } => {
+                warn!(
+                    "Webhook delivery {} to {} failed ({}); retrying in {}s",
+                    delivery.delivery_id,
+                    delivery.url,
+                    error,
+                    after.as_secs()
+                );
+                client
+                    .execute(
+                        r#"
+                        UPDATE webhook_deliveries
+                        SET attempts = attempts + 1, next_attempt_at = now() + make_interval(secs => $2),
+                            last_status_code = $3, last_error = $4
+                        WHERE delivery_id = $1
+                        "#,
+                        &[
+                            &delivery.delivery_id,
+                            &after.as_secs_f64(),
+                            &status_code.map(i32::from),
+                            error,
+                        ],
+                    )
+                    .await
+            }
+            AttemptOutcome::Dead { status_code, error } => {
+                error!(
+                    "Webhook delivery {} to {} dead-lettered after {} attempts ({})",
+                    delivery.delivery_id,
+                    delivery.url,
+                    delivery.attempts + 1,
+                    error
+                );
+                client
+                    .execute(
+                        r#"
+                        UPDATE webhook_deliveries
+                        SET status = 'dead', attempts = attempts + 1, last_status_code = $2, last_error = $3
+                        WHERE delivery_id = $1
+                        "#,
+                        &[&delivery.delivery_id, &status_code.map(i32::from), error],
+                    )
+                    .await
+            }
+        };
+        result
+            .map(|_| ())
+            .map_err(|e| format!("Failed to record webhook delivery {}: {e}", delivery.delivery_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_secs(10),
+            max_delay: Duration::from_secs(15),
+        }
+    }
+
+    #[test]
+    fn success_and_redirect_classification() {
+        assert_eq!(classify(0, Ok(204), &policy()), AttemptOutcome::Delivered { status_code: 204 });
+        match classify(0, Ok(302), &policy()) {
+            AttemptOutcome::Retry { after, status_code, .. } => {
+                assert_eq!(after, Duration::from_secs(10));
+                assert_eq!(status_code, Some(302));
+            }
+            other => panic!("redirects must not count as delivered: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn backoff_is_capped_and_exhaustion_dead_letters() {
+        match classify(1, Err("connection refused".into()), &policy()) {
+            AttemptOutcome::Retry { after, status_code, .. } => {
+                assert_eq!(after, Duration::from_secs(15));
+                assert_eq!(status_code, None);
+            }
+            other => panic!("expected retry, got {:?}", other),
+        }
+        assert!(matches!(classify(2, Ok(500), &policy()), AttemptOutcome::Dead { .. }));
+    }
+
+    #[test]
+    fn long_errors_are_truncated_on_char_boundary() {
+        let long = "é".repeat(MAX_ERROR_LEN);
+        match classify(0, Err(long), &policy()) {
+            AttemptOutcome::Retry { error, .. } => assert!(error.len() <= MAX_ERROR_LEN),
+            other => panic!("expected retry, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,130 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/tests/audit_chain_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Concurrency test for immutable_audit_log chain extension - orchestrator and ingest writers on several connections must produce one linear chain
+
+/*
+ * Audit Chain Concurrency Test
+ *
+ * The orchestrator database layer is compiled into the binaries, not the engine library, so it is
+ * included here by path. CI (or a developer) runs this target against a disposable database with
+ * the authoritative schema applied:
+ *
+ *   RANSOMEYE_TEST_AUDIT_DB=1 DB_HOST=... DB_PORT=... DB_NAME=... DB_USER=... DB_PASS=... \
+ *       cargo test -p engine --features future-policy --test audit_chain_tests
+ *
+ * Without RANSOMEYE_TEST_AUDIT_DB=1 it skips, so the default test run needs no database.
+ * db.rs and the engine binaries (built for every integration test) need the policy engine,
+ * so the target only builds with future-policy.
+ */
+
+#![cfg(feature = "future-policy")]
+
+#[allow(dead_code)]
+#[path = "../orchestrator/src/db.rs"]
+mod db;
+
+#[cfg(test)]
+mod tests {
+    use super::db::{CoreDb, DbConfig};
+    use ingest::storage::postgres::insert_immutable_audit_log;
+    use sha2::{Digest, Sha256};
+    use std::sync::Arc;
+    use tokio_postgres::NoTls;
+    use uuid::Uuid;
+
+    const ACTION: &str = "audit_chain_concurrency_test";
+
+    /// Hammers chain extension from several connections (cross-process path) and several tasks per
+    /// connection (in-process path), through both the orchestrator and the ingest writer, then
+    /// proves the rows form one linear chain.
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn audit_chain_is_linear_under_concurrent_writers() {
+        if std::env::var("RANSOMEYE_TEST_AUDIT_DB").as_deref() != Ok("1") {
+            eprintln!("skipping: RANSOMEYE_TEST_AUDIT_DB != 1");
+            return;
+        }
+        const CONNECTIONS: usize = 4;
+        const TASKS_PER_CONNECTION: usize = 4;
+        const INSERTS_PER_TASK: usize = 25;
+
+        let cfg = DbConfig::from_env_strict().expect("DB env");
+        let run_id = Uuid::new_v4().to_string();
+        let mut handles = Vec::new();
+        for c in 0..CONNECTIONS {
+            let db = Arc::new(CoreDb::connect_strict(&cfg).await.expect("connect"));
+            for t in 0..TASKS_PER_CONNECTION {
+                let db = db.clone();
+                let run_id = run_id.clone();
+                handles.push(tokio::spawn(async move {
+                    for i in 0..INSERTS_PER_TASK {
+                        let payload = serde_json::json!({ "run_id": run_id, "conn": c, "task": t, "i": i });
+                        db.insert_immutable_audit_log(None, ACTION, "other", None, &payload)
+                            .await
+                            .expect("audit insert");
+                    }
+                }));
+            }
+        }
+        // Ingest writer: one transaction per row on its own connection, same lock key
+        for c in 0..CONNECTIONS {
+            let (client, connection) = tokio_postgres::connect(&cfg.connection_string(), NoTls).await.expect("connect");
+            tokio::spawn(connection);
+            client.batch_execute("SET search_path = ransomeye, public;").await.expect("search_path");
+            let run_id = run_id.clone();
+            handles.push(tokio::spawn(async move {
+                for i in 0..INSERTS_PER_TASK {
+                    let payload = serde_json::json!({ "run_id": run_id, "ingest_conn": c, "i": i });
+                    let payload_sha256 = Sha256::digest(serde_json::to_string(&payload).unwrap().as_bytes()).to_vec();
+                    client.batch_execute("BEGIN").await.expect("begin");
+                    let inserted = insert_immutable_audit_log(
+                        &client,
+                        None,
+                        None,
+                        ACTION,
+                        "other",
+                        None,
+                        None,
+                        &payload,
+                        &payload_sha256,
+                    )
+                    .await
+                    .map_err(|e| e.to_string());
+                    inserted.expect("ingest audit insert");
+                    client.batch_execute("COMMIT").await.expect("commit");
+                }
+            }));
+        }
+        for h in handles {
+            h.await.expect("writer task");
+        }
+
+        let db = CoreDb::connect_strict(&cfg).await.expect("connect");
+        let rows = db
+            .client()
+            .query(
+                r#"
+                SELECT audit_id, prev_audit_id, chain_hash_sha256, payload_sha256
+                FROM immutable_audit_log
+                WHERE action = $1 AND payload_json->>'run_id' = $2
+                ORDER BY created_at ASC, audit_id ASC
+                "#,
+                &[&ACTION, &run_id],
+            )
+            .await
+            .expect("query chain");
+        assert_eq!(rows.len(), (TASKS_PER_CONNECTION + 1) * CONNECTIONS * INSERTS_PER_TASK);
+
+        // Every row's predecessor is the previous row (no forks, no gaps), and hashes recompute.
+        for w in rows.windows(2) {
+            let prev_id: Uuid = w[0].get(0);
+            let prev_chain: Vec<u8> = w[0].get(2);
+            let linked: Option<Uuid> = w[1].get(1);
+            let payload_sha: Vec<u8> = w[1].get(3);
+            let chain: Vec<u8> = w[1].get(2);
+            assert_eq!(linked, Some(prev_id), "audit chain forked or reordered");
+            let mut input = prev_chain.clone();
+            input.extend_from_slice(&payload_sha);
+            assert_eq!(chain, Sha256::digest(&input).to_vec());
+        }
+    }
+}
@@ -0,0 +1,104 @@
+// Path and File Name : /home/ransomeye/rebuild/core/engine/tests/schema_provenance_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for schema provenance recorded by the orchestrator - incremental applies in schema_migrations and contract validation outcomes in schema_validations
+
+/*
+ * Schema Provenance Tests
+ *
+ * The orchestrator database layer is compiled into the binaries, not the engine library, so it is
+ * included here by path. The test drops and re-creates a table, so it needs a disposable
+ * database with the authoritative schema applied:
+ *
+ *   RANSOMEYE_TEST_SCHEMA_DB=1 DB_HOST=... DB_PORT=... DB_NAME=... DB_USER=... DB_PASS=... \
+ *       cargo test -p engine --features future-policy --test schema_provenance_tests
+ *
+ * RANSOMEYE_SCHEMA_SQL_PATH defaults to the schema in this tree. Without
+ * RANSOMEYE_TEST_SCHEMA_DB=1 it skips.
+ * db.rs and the engine binaries (built for every integration test) need the policy engine,
+ * so the target only builds with future-policy.
+ */
+
+#![cfg(feature = "future-policy")]
+
+#[allow(dead_code)]
+#[path = "../orchestrator/src/db.rs"]
+mod db;
+
+#[cfg(test)]
+mod tests {
+    use super::db::{contract_sha256_hex, CoreDb, DbConfig};
+
+    /// Table dropped to simulate an incomplete schema (nothing references it)
+    const PROBE_TABLE: &str = "db_size_samples";
+
+    async fn connect() -> Option<(CoreDb, String)> {
+        if std::env::var("RANSOMEYE_TEST_SCHEMA_DB").as_deref() != Ok("1") {
+            eprintln!("skipping: RANSOMEYE_TEST_SCHEMA_DB != 1");
+            return None;
+        }
+        if std::env::var("RANSOMEYE_SCHEMA_SQL_PATH").is_err() {
+            std::env::set_var(
+                "RANSOMEYE_SCHEMA_SQL_PATH",
+                concat!(env!("CARGO_MANIFEST_DIR"), "/../../ransomeye_db_core/schema/schema.sql"),
+            );
+        }
+        let schema = std::fs::read(std::env::var("RANSOMEYE_SCHEMA_SQL_PATH").unwrap()).expect("schema file");
+        let db = CoreDb::connect_strict(&DbConfig::from_env_strict().expect("DB env")).await.expect("connect");
+        db.apply_authoritative_schema_from_env().await.expect("schema apply");
+        Some((db, contract_sha256_hex(&schema)))
+    }
+
+    async fn latest_validation(db: &CoreDb) -> (String, String, Option<String>) {
+        let row = db
+            .client()
+            .query_one(
+                "SELECT contract_sha256, status, detail FROM schema_validations ORDER BY validated_at DESC LIMIT 1",
+                &[],
+            )
+            .await
+            .expect("schema_validations row");
+        (row.get(0), row.get(1), row.get(2))
+    }
+
+    #[tokio::test]
+    async fn test_schema_provenance_recorded() {
+        let Some((db, contract)) = connect().await else { return };
+        let migrations = || async {
+            db.client()
+                .query_one("SELECT count(*) FROM schema_migrations", &[])
+                .await
+                .expect("count migrations")
+                .get::<_, i64>(0)
+        };
+
+        // A complete schema is left alone and records nothing
+        let before = migrations().await;
+        db.apply_authoritative_schema_from_env().await.expect("schema apply");
+        assert_eq!(migrations().await, before);
+
+        db.client().batch_execute(&format!("DROP TABLE {PROBE_TABLE}")).await.expect("drop probe table");
+        let err = db.validate_schema_contract().await.expect_err("validation passed with a table missing");
+        assert!(err.contains(PROBE_TABLE), "{err}");
+        let (sha, status, detail) = latest_validation(&db).await;
+        assert_eq!((sha.as_str(), status.as_str()), (contract.as_str(), "failed"));
+        assert_eq!(detail.as_deref(), Some(err.as_str()));
+
+        // Incremental apply re-creates only the missing table and records it
+        db.apply_authoritative_schema_from_env().await.expect("incremental apply");
+        let row = db
+            .client()
+            .query_one(
+                "SELECT contract_sha256, kind, tables_applied FROM schema_migrations ORDER BY applied_at DESC LIMIT 1",
+                &[],
+            )
+            .await
+            .expect("schema_migrations row");
+        assert_eq!(row.get::<_, String>(0), contract);
+        assert_eq!(row.get::<_, String>(1), "incremental");
+        assert_eq!(row.get::<_, Vec<String>>(2), vec![PROBE_TABLE.to_string()]);
+        assert_eq!(migrations().await, before + 1);
+
+        db.validate_schema_contract().await.expect("validation after incremental apply");
+        assert_eq!(latest_validation(&db).await, (contract, "passed".to_string(), None));
+    }
+}
@@ -1,12 +1,13 @@
 // Path and File Name : /home/ransomeye/rebuild/core/governor/src/lib.rs
 // Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
-// Details of functionality of this file: Resource governance orchestrator - coordinates CPU, memory, disk, network, and degradation governance across all RansomEye components
+// Details of functionality of this file: Resource governance orchestrator - coordinates CPU, memory, disk, network, and degradation governance across all RansomEye components; directory quotas for spools and evidence stores
 
 pub mod cpu;
 pub mod memory;
 pub mod disk;
 pub mod network;
 pub mod degradation;
+pub mod quota;
 
 use cpu::{CpuGovernor, ComponentPriority as CpuPriority};
 use memory::{MemoryGovernor, MemoryGovernanceError};
@@ -0,0 +1,575 @@
+// Path and File Name : /home/ransomeye/rebuild/core/governor/src/quota.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Directory quotas - per-directory usage tracking against configured budgets, early warning level, overflow policies (reject new writes or purge oldest non-held entries) and purge planning for spool directories
+
+/*
+ * Directory Quotas
+ *
+ * A QuotaManager watches a fixed set of named directories (evidence spools, WALs). Each has a
+ * byte budget and an overflow policy:
+ *
+ *   reject        writes that would take the directory over its budget are refused
+ *   purge_oldest  writes are accepted; once the directory reaches its budget the owner purges
+ *                 the oldest entries that are not held, down to the warning level. Writes are
+ *                 only refused when a purge could not get back under budget (everything left
+ *                 is held).
+ *
+ * Levels: ok below warn_percent of the budget, warning from there, exceeded at the budget.
+ * Usage is measured by `scan` (a walk of the directory) and kept current between scans by
+ * `record_write` / `record_purge`. What counts as "held" is the owner's decision: the manager
+ * only plans the purge (`plan_purge`) and never reads a database.
+ */
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::warn;
+
+pub const DEFAULT_WARN_PERCENT: u8 = 80;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum QuotaError {
+    #[error("Invalid quota specification: {0}")]
+    InvalidSpec(String),
+    #[error("Directory quota exceeded for {name}: {used_bytes} of {budget_bytes} bytes used")]
+    Exceeded { name: String, used_bytes: u64, budget_bytes: u64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    Reject,
+    PurgeOldest,
+}
+
+impl OverflowPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OverflowPolicy::Reject => "reject",
+            OverflowPolicy::PurgeOldest => "purge_oldest",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "reject" => Some(OverflowPolicy::Reject),
+            "purge_oldest" => Some(OverflowPolicy::PurgeOldest),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaLevel {
+    Ok,
+    Warning,
+    Exceeded,
+}
+
+impl QuotaLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuotaLevel::Ok => "ok",
+            QuotaLevel::Warning => "warning",
+            QuotaLevel::Exceeded => "exceeded",
+        }
+    }
+
+    /// Level of `used_bytes` against `budget_bytes` with the warning at `warn_percent`.
+    pub fn of(used_bytes: u64, budget_bytes: u64, warn_percent: u8) -> Self {
+        if used_bytes >= budget_bytes {
+            QuotaLevel::Exceeded
+        } else if used_bytes >= warn_bytes(budget_bytes, warn_percent) {
+            QuotaLevel::Warning
+        } else {
+            QuotaLevel::Ok
+        }
+    }
+}
+
+/// Usage at which a budget enters the warning level (and down to which a purge frees space).
+pub fn warn_bytes(budget_bytes: u64, warn_percent: u8) -> u64 {
+    (budget_bytes as u128 * warn_percent as u128 / 100) as u64
+}
+
+/// One entry of a quota list: `name:budget_mb[:policy]` (policy defaults to reject).
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuotaSpec {
+    pub name: String,
+    pub budget_bytes: u64,
+    pub policy: OverflowPolicy,
+}
+
+/// Parse a comma-separated quota list, e.g. `pcap_spool:10240:purge_oldest,agent_log_spool:2048`.
+pub fn parse_specs(value: &str) -> Result<Vec<QuotaSpec>, QuotaError> {
+    let mut specs: Vec<QuotaSpec> = Vec::new();
+    for item in value.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let parts: Vec<&str> = item.split(':').map(str::trim).collect();
+        if parts.len() < 2 || parts.len() > 3 || parts[0].is_empty() {
+            return Err(QuotaError::InvalidSpec(format!("'{}' is not name:budget_mb[:policy]", item)));
+        }
+        let budget_mb = parts[1]
+            .parse::<u64>()
+            .ok()
+            .filter(|mb| *mb > 0)
+            .ok_or_else(|| QuotaError::InvalidSpec(format!("'{}': budget must be a positive number of MiB", item)))?;
+        let policy = match parts.get(2) {
+            Some(p) => OverflowPolicy::parse(p).ok_or_else(|| {
+                QuotaError::InvalidSpec(format!("'{}': policy must be reject or purge_oldest", item))
+            })?,
+            None => OverflowPolicy::Reject,
+        };
+        if specs.iter().any(|s| s.name == parts[0]) {
+            return Err(QuotaError::InvalidSpec(format!("'{}' is listed twice", parts[0])));
+        }
+        specs.push(QuotaSpec {
+            name: parts[0].to_string(),
+            budget_bytes: budget_mb.saturating_mul(1024 * 1024),
+            policy,
+        });
+    }
+    Ok(specs)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirectoryQuota {
+    pub name: String,
+    pub path: PathBuf,
+    pub budget_bytes: u64,
+    pub policy: OverflowPolicy,
+}
+
+/// Current state of one directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuotaUsage {
+    pub name: String,
+    pub path: String,
+    pub policy: OverflowPolicy,
+    pub budget_bytes: u64,
+    pub used_bytes: u64,
+    pub level: QuotaLevel,
+    /// Last completed scan (None before the first)
+    pub scanned_at: Option<DateTime<Utc>>,
+    /// Writes refused since start
+    pub rejected_writes: u64,
+    /// Entries and bytes purged since start
+    pub purged_entries: u64,
+    pub purged_bytes: u64,
+    /// Entries the last purge had to keep because they are held
+    pub held_entries: u64,
+    /// A purge could not get back under budget: writes are refused until a scan shows room
+    pub purge_exhausted: bool,
+}
+
+/// A level change seen by `scan`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuotaTransition {
+    pub name: String,
+    pub from: QuotaLevel,
+    pub to: QuotaLevel,
+    pub used_bytes: u64,
+    pub budget_bytes: u64,
+}
+
+struct DirectoryState {
+    quota: DirectoryQuota,
+    used_bytes: u64,
+    /// Level as of the last scan (transitions are reported against it)
+    scanned_level: QuotaLevel,
+    scanned_at: Option<DateTime<Utc>>,
+    rejected_writes: u64,
+    purged_entries: u64,
+    purged_bytes: u64,
+    held_entries: u64,
+    purge_exhausted: bool,
+}
+
+pub struct QuotaManager {
+    warn_percent: u8,
+    dirs: RwLock<HashMap<String, DirectoryState>>,
+}
+
+impl QuotaManager {
+    pub fn new(quotas: Vec<DirectoryQuota>, warn_percent: u8) -> Result<Self, QuotaError> {
+        if !(1..=99).contains(&warn_percent) {
+            return Err(QuotaError::InvalidSpec(format!("warning level {}% must be 1-99", warn_percent)));
+        }
+        let mut dirs = HashMap::new();
+        for quota in quotas {
+            if quota.budget_bytes == 0 {
+                return Err(QuotaError::InvalidSpec(format!("{}: budget must be positive", quota.name)));
+            }
+            let name = quota.name.clone();
+            let state = DirectoryState {
+                quota,
+                used_bytes: 0,
+                scanned_level: QuotaLevel::Ok,
+                scanned_at: None,
+                rejected_writes: 0,
+                purged_entries: 0,
+                purged_bytes: 0,
+                held_entries: 0,
+                purge_exhausted: false,
+            };
+            if dirs.insert(name.clone(), state).is_some() {
+                return Err(QuotaError::InvalidSpec(format!("'{}' is listed twice", name)));
+            }
+        }
+        Ok(Self { warn_percent, dirs: RwLock::new(dirs) })
+    }
+
+    pub fn warn_percent(&self) -> u8 {
+        self.warn_percent
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dirs.read().is_empty()
+    }
+
+    pub fn quota(&self, name: &str) -> Option<DirectoryQuota> {
+        self.dirs.read().get(name).map(|d| d.quota.clone())
+    }
+
+    /// Measure every directory; returns the level changes since the previous scan.
+    /// A directory that cannot be read keeps its last usage (and is logged).
+    pub fn scan(&self) -> Vec<QuotaTransition> {
+        let paths: Vec<(String, PathBuf)> =
+            self.dirs.read().values().map(|d| (d.quota.name.clone(), d.quota.path.clone())).collect();
+        let measured: Vec<(String, Option<u64>)> = paths
+            .into_iter()
+            .map(|(name, path)| match directory_usage(&path) {
+                Ok(bytes) => (name, Some(bytes)),
+                Err(e) => {
+                    warn!("Cannot measure quota directory {} ({}): {}", name, path.display(), e);
+                    (name, None)
+                }
+            })
+            .collect();
+
+        let now = Utc::now();
+        let mut transitions = Vec::new();
+        let mut dirs = self.dirs.write();
+        for (name, bytes) in measured {
+            let Some(dir) = dirs.get_mut(&name) else { continue };
+            let Some(bytes) = bytes else { continue };
+            dir.used_bytes = bytes;
+            dir.scanned_at = Some(now);
+            if bytes < dir.quota.budget_bytes {
+                dir.purge_exhausted = false;
+            }
+            let level = QuotaLevel::of(bytes, dir.quota.budget_bytes, self.warn_percent);
+            if level != dir.scanned_level {
+                transitions.push(QuotaTransition {
+                    name: name.clone(),
+                    from: dir.scanned_level,
+                    to: level,
+                    used_bytes: bytes,
+                    budget_bytes: dir.quota.budget_bytes,
+                });
+                dir.scanned_level = level;
+            }
+        }
+        transitions.sort_by(|a, b| a.name.cmp(&b.name));
+        transitions
+    }
+
+    /// May `bytes` more be written to `name`? Unknown names have no quota.
+    pub fn admit(&self, name: &str, bytes: u64) -> Result<(), QuotaError> {
+        let mut dirs = self.dirs.write();
+        let Some(dir) = dirs.get_mut(name) else { return Ok(()) };
+        let over = dir.used_bytes.saturating_add(bytes) > dir.quota.budget_bytes;
+        let refuse = match dir.quota.policy {
+            OverflowPolicy::Reject => over,
+            OverflowPolicy::PurgeOldest => over && dir.purge_exhausted,
+        };
+        if refuse {
+            dir.rejected_writes += 1;
+            return Err(QuotaError::Exceeded {
+                name: name.to_string(),
+                used_bytes: dir.used_bytes,
+                budget_bytes: dir.quota.budget_bytes,
+            });
+        }
+        Ok(())
+    }
+
+    /// Count an accepted write until the next scan measures it.
+    pub fn record_write(&self, name: &str, bytes: u64) {
+        if let Some(dir) = self.dirs.write().get_mut(name) {
+            dir.used_bytes = dir.used_bytes.saturating_add(bytes);
+        }
+    }
+
+    /// Directories with the purge_oldest policy at or over budget, with the usage to purge down to.
+    pub fn purge_due(&self) -> Vec<(DirectoryQuota, u64, u64)> {
+        let mut due: Vec<(DirectoryQuota, u64, u64)> = self
+            .dirs
+            .read()
+            .values()
+            .filter(|d| d.quota.policy == OverflowPolicy::PurgeOldest && d.used_bytes >= d.quota.budget_bytes)
+            .map(|d| (d.quota.clone(), d.used_bytes, warn_bytes(d.quota.budget_bytes, self.warn_percent)))
+            .collect();
+        due.sort_by(|a, b| a.0.name.cmp(&b.0.name));
+        due
+    }
+
+    /// Account for a purge of `name`.
+    pub fn record_purge(&self, name: &str, plan: &PurgePlan) {
+        if let Some(dir) = self.dirs.write().get_mut(name) {
+            dir.used_bytes = dir.used_bytes.saturating_sub(plan.bytes);
+            dir.purged_entries += plan.entries.len() as u64;
+            dir.purged_bytes += plan.bytes;
+            dir.held_entries = plan.held_skipped;
+            dir.purge_exhausted = dir.used_bytes >= dir.quota.budget_bytes;
+        }
+    }
+
+    pub fn usage(&self) -> Vec<QuotaUsage> {
+        let mut out: Vec<QuotaUsage> = self
+            .dirs
+            .read()
+            .values()
+            .map(|d| QuotaUsage {
+                name: d.quota.name.clone(),
+                path: d.quota.path.display().to_string(),
+                policy: d.quota.policy,
+                budget_bytes: d.quota.budget_bytes,
+                used_bytes: d.used_bytes,
+                level: QuotaLevel::of(d.used_bytes, d.quota.budget_bytes, self.warn_percent),
+                scanned_at: d.scanned_at,
+                rejected_writes: d.rejected_writes,
+                purged_entries: d.purged_entries,
+                purged_bytes: d.purged_bytes,
+                held_entries: d.held_entries,
+                purge_exhausted: d.purge_exhausted,
+            })
+            .collect();
+        out.sort_by(|a, b| a.name.cmp(&b.name));
+        out
+    }
+}
+
+/// Bytes of regular files under `path` (symlinks are not followed). A missing directory is empty.
+pub fn directory_usage(path: &Path) -> io::Result<u64> {
+    let meta = match fs::symlink_metadata(path) {
+        Ok(m) => m,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+    if !meta.is_dir() {
+        return Ok(if meta.is_file() { meta.len() } else { 0 });
+    }
+    let mut total = 0u64;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        // Entries removed mid-walk (an import or purge running alongside) count as empty
+        match directory_usage(&entry.path()) {
+            Ok(bytes) => total = total.saturating_add(bytes),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(total)
+}
+
+/// Files and directories of a spool that belong together: `<key>.pcap` and `<key>.json` or a
+/// `<key>/` chunk directory share the key before the first '.'.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpoolEntry {
+    pub key: String,
+    pub paths: Vec<PathBuf>,
+    pub bytes: u64,
+    /// Newest modification time of its parts
+    pub modified: SystemTime,
+}
+
+/// Top-level entries of `dir` grouped by key. Hidden files are skipped.
+pub fn spool_entries(dir: &Path) -> io::Result<Vec<SpoolEntry>> {
+    let mut by_key: HashMap<String, SpoolEntry> = HashMap::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if file_name.starts_with('.') {
+            continue;
+        }
+        let key = file_name.split('.').next().unwrap_or_default().to_string();
+        let meta = match entry.path().symlink_metadata() {
+            Ok(m) => m,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        let bytes = match directory_usage(&entry.path()) {
+            Ok(b) => b,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        let modified = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        let group = by_key.entry(key.clone()).or_insert_with(|| SpoolEntry {
+            key,
+            paths: Vec::new(),
+            bytes: 0,
+            modified: SystemTime::UNIX_EPOCH,
+        });
+        group.paths.push(entry.path());
+        group.bytes = group.bytes.saturating_add(bytes);
+        group.modified = group.modified.max(modified);
+    }
+    let mut entries: Vec<SpoolEntry> = by_key.into_values().collect();
+    entries.sort_by(|a, b| a.modified.cmp(&b.modified).then_with(|| a.key.cmp(&b.key)));
+    Ok(entries)
+}
+
+/// Delete every part of a spool entry.
+pub fn remove_spool_entry(entry: &SpoolEntry) -> io::Result<()> {
+    for path in &entry.paths {
+        let result = match path.symlink_metadata() {
+            Ok(m) if m.is_dir() => fs::remove_dir_all(path),
+            Ok(_) => fs::remove_file(path),
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// What a purge will delete.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PurgePlan {
+    pub entries: Vec<SpoolEntry>,
+    pub bytes: u64,
+    /// Entries older than the last one purged (or all, if the target was not reached) that were kept because held
+    pub held_skipped: u64,
+}
+
+/// Oldest entries first, skipping held ones, until usage is at or below `target_bytes`.
+pub fn plan_purge(
+    entries: &[SpoolEntry],
+    is_held: impl Fn(&SpoolEntry) -> bool,
+    used_bytes: u64,
+    target_bytes: u64,
+) -> PurgePlan {
+    let mut ordered: Vec<&SpoolEntry> = entries.iter().collect();
+    ordered.sort_by(|a, b| a.modified.cmp(&b.modified).then_with(|| a.key.cmp(&b.key)));
+
+    let mut plan = PurgePlan::default();
+    let mut remaining = used_bytes;
+    for entry in ordered {
+        if remaining <= target_bytes {
+            break;
+        }
+        if is_held(entry) {
+            plan.held_skipped += 1;
+            continue;
+        }
+        remaining = remaining.saturating_sub(entry.bytes);
+        plan.bytes += entry.bytes;
+        plan.entries.push(entry.clone());
+    }
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const MIB: u64 = 1024 * 1024;
+
+    fn entry(key: &str, bytes: u64, age_secs: u64) -> SpoolEntry {
+        SpoolEntry {
+            key: key.to_string(),
+            paths: Vec::new(),
+            bytes,
+            modified: SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000 - age_secs),
+        }
+    }
+
+    fn manager(policy: OverflowPolicy) -> QuotaManager {
+        let quota = DirectoryQuota {
+            name: "spool".to_string(),
+            path: PathBuf::from("/nonexistent/ransomeye-quota-test"),
+            budget_bytes: 100,
+            policy,
+        };
+        QuotaManager::new(vec![quota], 80).unwrap()
+    }
+
+    #[test]
+    fn test_parse_specs() {
+        let specs = parse_specs("pcap_spool:10:purge_oldest, agent_log_spool:2").unwrap();
+        assert_eq!(specs.len(), 2);
+        assert_eq!(specs[0].budget_bytes, 10 * MIB);
+        assert_eq!(specs[0].policy, OverflowPolicy::PurgeOldest);
+        assert_eq!(specs[1].policy, OverflowPolicy::Reject);
+        assert!(parse_specs("").unwrap().is_empty());
+
+        assert!(parse_specs("pcap_spool").is_err());
+        assert!(parse_specs("pcap_spool:0").is_err());
+        assert!(parse_specs("pcap_spool:10:drop").is_err());
+        assert!(parse_specs("pcap_spool:10,pcap_spool:20").is_err());
+    }
+
+    #[test]
+    fn test_levels() {
+        assert_eq!(QuotaLevel::of(79, 100, 80), QuotaLevel::Ok);
+        assert_eq!(QuotaLevel::of(80, 100, 80), QuotaLevel::Warning);
+        assert_eq!(QuotaLevel::of(100, 100, 80), QuotaLevel::Exceeded);
+    }
+
+    #[test]
+    fn test_reject_policy_refuses_writes_over_budget() {
+        let quotas = manager(OverflowPolicy::Reject);
+        quotas.record_write("spool", 90);
+        assert!(quotas.admit("spool", 10).is_ok());
+        assert!(matches!(quotas.admit("spool", 11), Err(QuotaError::Exceeded { .. })));
+        assert!(quotas.admit("other", u64::MAX).is_ok());
+        assert_eq!(quotas.usage()[0].rejected_writes, 1);
+        assert!(quotas.purge_due().is_empty());
+    }
+
+    #[test]
+    fn test_purge_policy_refuses_only_when_purge_is_exhausted() {
+        let quotas = manager(OverflowPolicy::PurgeOldest);
+        quotas.record_write("spool", 120);
+        assert!(quotas.admit("spool", 10).is_ok());
+        let due = quotas.purge_due();
+        assert_eq!(due.len(), 1);
+        assert_eq!((due[0].1, due[0].2), (120, 80));
+
+        // Everything left is held: nothing freed, writes refused until a scan shows room
+        quotas.record_purge("spool", &PurgePlan { entries: Vec::new(), bytes: 0, held_skipped: 3 });
+        assert!(quotas.admit("spool", 1).is_err());
+        assert!(quotas.usage()[0].purge_exhausted);
+
+        // The scan finds the directory empty (nonexistent): back to ok
+        let transitions = quotas.scan();
+        assert_eq!(transitions.len(), 0);
+        assert!(quotas.admit("spool", 1).is_ok());
+    }
+
+    #[test]
+    fn test_plan_purge_oldest_first_skipping_held() {
+        let entries = vec![entry("new", 30, 10), entry("held", 30, 300), entry("old", 30, 200), entry("mid", 30, 100)];
+        let plan = plan_purge(&entries, |e| e.key == "held", 120, 60);
+        let keys: Vec<&str> = plan.entries.iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(keys, vec!["old", "mid"]);
+        assert_eq!(plan.bytes, 60);
+        assert_eq!(plan.held_skipped, 1);
+
+        let none = plan_purge(&entries, |_| true, 120, 60);
+        assert!(none.entries.is_empty());
+        assert_eq!(none.held_skipped, 4);
+    }
+}
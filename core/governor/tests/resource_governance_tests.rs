@@ -395,3 +395,55 @@ fn test_comprehensive_resource_check() {
     assert!(metrics.is_ok());
 }
 
+
+#[test]
+fn test_directory_quota_scan_and_spool_purge() {
+    use governor::quota::{self, DirectoryQuota, OverflowPolicy, QuotaLevel, QuotaManager};
+
+    let temp_dir = TempDir::new().unwrap();
+    let spool = temp_dir.path().join("spool");
+    std::fs::create_dir_all(spool.join("c3")).unwrap();
+    std::fs::write(spool.join("a1.pcap"), vec![0u8; 400]).unwrap();
+    std::fs::write(spool.join("a1.json"), vec![0u8; 100]).unwrap();
+    std::fs::write(spool.join("b2.report"), vec![0u8; 300]).unwrap();
+    std::fs::write(spool.join("c3").join("00000000.chunk"), vec![0u8; 250]).unwrap();
+    std::fs::write(spool.join(".partial"), vec![0u8; 50]).unwrap();
+
+    let quotas = QuotaManager::new(
+        vec![DirectoryQuota {
+            name: "pcap_spool".to_string(),
+            path: spool.clone(),
+            budget_bytes: 1000,
+            policy: OverflowPolicy::PurgeOldest,
+        }],
+        80,
+    )
+    .unwrap();
+
+    let transitions = quotas.scan();
+    assert_eq!(transitions.len(), 1);
+    assert_eq!(transitions[0].to, QuotaLevel::Exceeded);
+    assert_eq!(transitions[0].used_bytes, 1100);
+
+    // Hidden files count towards usage but are never purge candidates; equal ages purge in key order
+    let entries = quota::spool_entries(&spool).unwrap();
+    let mut keys: Vec<&str> = entries.iter().map(|e| e.key.as_str()).collect();
+    keys.sort();
+    assert_eq!(keys, vec!["a1", "b2", "c3"]);
+    assert_eq!(entries.iter().find(|e| e.key == "a1").unwrap().paths.len(), 2);
+
+    let (quota, used, target) = quotas.purge_due().remove(0);
+    assert_eq!(quota.name, "pcap_spool");
+    let plan = quota::plan_purge(&entries, |e| e.key == "b2", used, target);
+    for entry in &plan.entries {
+        quota::remove_spool_entry(entry).unwrap();
+    }
+    quotas.record_purge("pcap_spool", &plan);
+
+    assert!(spool.join("b2.report").exists());
+    assert!(!spool.join("a1.pcap").exists() && !spool.join("a1.json").exists());
+    assert_eq!(quota::directory_usage(&spool).unwrap(), 600);
+    let transitions = quotas.scan();
+    assert_eq!(transitions[0].to, QuotaLevel::Ok);
+    assert!(quotas.admit("pcap_spool", 400).is_ok());
+}
@@ -0,0 +1,245 @@
+// Path and File Name : /home/ransomeye/rebuild/core/health/src/lib.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Health model shared by every RansomEye component - status levels, per-subsystem breakdown and metrics in one report, with the mapping onto component_health rows
+
+//! A component builds one [`HealthReport`] from its subsystems:
+//!
+//! ```
+//! use health::{HealthReport, HealthStatus};
+//!
+//! let report = HealthReport::new("ransomeye_ingestion", "core-01")
+//!     .with_subsystem("pipeline", HealthStatus::Healthy, None)
+//!     .with_subsystem("orchestrator_link", HealthStatus::Degraded, Some("no acknowledgement for 95s".into()))
+//!     .with_metric("in_flight_requests", 3.0);
+//! assert_eq!(report.status, HealthStatus::Degraded);
+//! assert!(report.status.is_serving());
+//! ```
+//!
+//! The overall status is the worst subsystem status (healthy < degraded < unknown < unhealthy).
+//! The four levels are the values of `component_health.status`, so a report is stored as
+//! [`HealthReport::status_details`] and [`HealthReport::metrics_json`] without translation and
+//! the orchestrator gate, `/v1/status`, ingest and agent reports mean the same thing by the same word.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+/// Health of a component or one of its subsystems.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    /// Working as configured
+    Healthy,
+    /// Serving, with reduced capability or a non-critical fault (e.g. events spooled, hooks missing)
+    Degraded,
+    /// Not serving, or losing data
+    Unhealthy,
+    /// Not determined (never reported, or the check itself failed)
+    Unknown,
+}
+
+impl HealthStatus {
+    pub const ALL: [HealthStatus; 4] = [HealthStatus::Healthy, HealthStatus::Degraded, HealthStatus::Unhealthy, HealthStatus::Unknown];
+
+    /// component_health.status value
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HealthStatus::Healthy => "healthy",
+            HealthStatus::Degraded => "degraded",
+            HealthStatus::Unhealthy => "unhealthy",
+            HealthStatus::Unknown => "unknown",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.as_str() == value)
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            HealthStatus::Healthy => 0,
+            HealthStatus::Degraded => 1,
+            HealthStatus::Unknown => 2,
+            HealthStatus::Unhealthy => 3,
+        }
+    }
+
+    /// The worse of two statuses.
+    pub fn worst(self, other: HealthStatus) -> HealthStatus {
+        if other.rank() > self.rank() {
+            other
+        } else {
+            self
+        }
+    }
+
+    /// Serving traffic: healthy or degraded.
+    pub fn is_serving(&self) -> bool {
+        matches!(self, HealthStatus::Healthy | HealthStatus::Degraded)
+    }
+
+    /// HTTP status of a health endpoint: 200 while serving, 503 otherwise.
+    pub fn http_status(&self) -> u16 {
+        if self.is_serving() {
+            200
+        } else {
+            503
+        }
+    }
+}
+
+impl fmt::Display for HealthStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// One part of a component (storage, pipeline, delivery, ...).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SubsystemHealth {
+    pub name: String,
+    pub status: HealthStatus,
+    /// Why the subsystem is not healthy, or what it is running with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Health of one component instance at one moment.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct HealthReport {
+    /// Component name (ransomeye_orchestrator, ransomeye_ingestion, linux_agent, ...)
+    pub component: String,
+    /// Instance of the component (hostname or agent component identity)
+    pub instance_id: String,
+    /// Worst subsystem status (healthy without subsystems)
+    pub status: HealthStatus,
+    pub observed_at: DateTime<Utc>,
+    #[serde(default)]
+    pub subsystems: Vec<SubsystemHealth>,
+    /// Numeric gauges and counters (queue depth, in-flight requests, ...); non-finite values are dropped
+    #[serde(default)]
+    pub metrics: BTreeMap<String, f64>,
+}
+
+impl HealthReport {
+    pub fn new(component: impl Into<String>, instance_id: impl Into<String>) -> Self {
+        Self {
+            component: component.into(),
+            instance_id: instance_id.into(),
+            status: HealthStatus::Healthy,
+            observed_at: Utc::now(),
+            subsystems: Vec::new(),
+            metrics: BTreeMap::new(),
+        }
+    }
+
+    /// Add a subsystem; the overall status becomes the worse of the two.
+    pub fn push_subsystem(&mut self, name: impl Into<String>, status: HealthStatus, detail: Option<String>) {
+        self.status = self.status.worst(status);
+        self.subsystems.push(SubsystemHealth { name: name.into(), status, detail });
+    }
+
+    pub fn with_subsystem(mut self, name: impl Into<String>, status: HealthStatus, detail: Option<String>) -> Self {
+        self.push_subsystem(name, status, detail);
+        self
+    }
+
+    pub fn set_metric(&mut self, name: impl Into<String>, value: f64) {
+        if value.is_finite() {
+            self.metrics.insert(name.into(), value);
+        }
+    }
+
+    pub fn with_metric(mut self, name: impl Into<String>, value: f64) -> Self {
+        self.set_metric(name, value);
+        self
+    }
+
+    pub fn subsystem(&self, name: &str) -> Option<&SubsystemHealth> {
+        self.subsystems.iter().find(|s| s.name == name)
+    }
+
+    /// component_health.status_details: the subsystems that are not healthy, worst first, e.g.
+    /// `disk: unhealthy (budget exceeded); delivery: degraded (circuit open)`. None when all are.
+    pub fn status_details(&self) -> Option<String> {
+        let mut faults: Vec<&SubsystemHealth> = self.subsystems.iter().filter(|s| s.status != HealthStatus::Healthy).collect();
+        if faults.is_empty() {
+            return None;
+        }
+        faults.sort_by_key(|s| std::cmp::Reverse(s.status.rank()));
+        Some(
+            faults
+                .iter()
+                .map(|s| match &s.detail {
+                    Some(detail) => format!("{}: {} ({})", s.name, s.status, detail),
+                    None => format!("{}: {}", s.name, s.status),
+                })
+                .collect::<Vec<_>>()
+                .join("; "),
+        )
+    }
+
+    /// component_health.metrics_json: subsystems and metrics.
+    pub fn metrics_json(&self) -> JsonValue {
+        serde_json::json!({
+            "component": self.component,
+            "instance_id": self.instance_id,
+            "subsystems": self.subsystems,
+            "metrics": self.metrics,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overall_status_is_the_worst_subsystem() {
+        let report = HealthReport::new("linux_agent", "host-01");
+        assert_eq!(report.status, HealthStatus::Healthy);
+        assert_eq!(report.status_details(), None);
+
+        let report = report
+            .with_subsystem("delivery", HealthStatus::Degraded, Some("circuit open".into()))
+            .with_subsystem("kernel_hooks", HealthStatus::Healthy, None);
+        assert_eq!(report.status, HealthStatus::Degraded);
+        let report = report.with_subsystem("liveness", HealthStatus::Unknown, None);
+        assert_eq!(report.status, HealthStatus::Unknown);
+        assert!(!report.status.is_serving());
+        let report = report
+            .with_subsystem("disk", HealthStatus::Unhealthy, Some("budget exceeded".into()))
+            .with_subsystem("yara", HealthStatus::Degraded, None);
+        assert_eq!(report.status, HealthStatus::Unhealthy);
+        assert_eq!(report.status.http_status(), 503);
+        assert_eq!(
+            report.status_details().unwrap(),
+            "disk: unhealthy (budget exceeded); liveness: unknown; delivery: degraded (circuit open); yara: degraded"
+        );
+    }
+
+    #[test]
+    fn report_round_trips_and_maps_onto_component_health() {
+        let report = HealthReport::new("ransomeye_ingestion", "core-01")
+            .with_subsystem("pipeline", HealthStatus::Healthy, None)
+            .with_metric("queued", 12.0)
+            .with_metric("ratio", f64::NAN);
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["status"], "healthy");
+        assert_eq!(json["subsystems"][0], serde_json::json!({"name": "pipeline", "status": "healthy"}));
+        assert_eq!(serde_json::from_value::<HealthReport>(json).unwrap(), report);
+
+        let metrics = report.metrics_json();
+        assert_eq!(metrics["metrics"], serde_json::json!({"queued": 12.0}));
+        for status in HealthStatus::ALL {
+            assert_eq!(HealthStatus::parse(status.as_str()), Some(status));
+        }
+        assert_eq!(HealthStatus::parse("HEALTHY"), None);
+    }
+}
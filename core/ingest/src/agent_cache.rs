@@ -0,0 +1,178 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/agent_cache.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: In-process TTL cache of producer identity -> agent_id resolution, taking the agents lookup and last_seen_at update off the per-event hot path
+
+/*
+ * Agent Identity Cache
+ *
+ * Unauthenticated (legacy) ingest resolves every event's component identity to an agents row:
+ * one SELECT plus a last_seen_at UPDATE (or an INSERT for a new identity). Resolved identities are
+ * cached per (agent type, identity) for RANSOMEYE_INGEST_AGENT_CACHE_TTL_SECS, so a busy agent
+ * costs the store one resolution per TTL; agents.last_seen_at therefore has TTL resolution.
+ *
+ * Failed resolutions are cached too, for RANSOMEYE_INGEST_AGENT_CACHE_NEGATIVE_TTL_SECS: while the
+ * store is failing, events of that identity are refused (fail-closed, as before) without each one
+ * issuing its own query. A failure never displaces an agent_id that is still fresh.
+ *
+ * Enrollment and identity-conflict resolution invalidate the identity, so the next event re-reads
+ * the agents row. The cache is bounded by RANSOMEYE_INGEST_AGENT_CACHE_MAX_ENTRIES; when full,
+ * expired entries are dropped first and the cache is cleared if that is not enough.
+ */
+
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::storage::{StorageError, TelemetrySource, TelemetryStore};
+
+const DEFAULT_TTL_SECS: u64 = 300;
+const DEFAULT_NEGATIVE_TTL_SECS: u64 = 5;
+const DEFAULT_MAX_ENTRIES: usize = 100_000;
+
+#[derive(Debug, Clone)]
+enum Resolution {
+    Agent(Uuid),
+    Failed(String),
+}
+
+#[derive(Debug, Clone)]
+struct CachedResolution {
+    resolution: Resolution,
+    at: Instant,
+}
+
+pub struct AgentIdentityCache {
+    /// Zero disables caching (every event resolves against the store)
+    ttl: Duration,
+    negative_ttl: Duration,
+    max_entries: usize,
+    entries: DashMap<(TelemetrySource, String), CachedResolution>,
+}
+
+fn env_secs(key: &str, default_value: u64) -> Result<u64, String> {
+    match std::env::var(key) {
+        Ok(v) => v.parse::<u64>().map_err(|_| format!("Invalid {} '{}' (expected seconds)", key, v)),
+        Err(_) => Ok(default_value),
+    }
+}
+
+impl AgentIdentityCache {
+    pub fn from_env() -> Result<Self, String> {
+        let ttl = env_secs("RANSOMEYE_INGEST_AGENT_CACHE_TTL_SECS", DEFAULT_TTL_SECS)?;
+        let negative_ttl = env_secs("RANSOMEYE_INGEST_AGENT_CACHE_NEGATIVE_TTL_SECS", DEFAULT_NEGATIVE_TTL_SECS)?;
+        let max_entries = match std::env::var("RANSOMEYE_INGEST_AGENT_CACHE_MAX_ENTRIES") {
+            Ok(v) => v
+                .parse::<usize>()
+                .ok()
+                .filter(|n| *n > 0)
+                .ok_or_else(|| format!("Invalid RANSOMEYE_INGEST_AGENT_CACHE_MAX_ENTRIES '{}'", v))?,
+            Err(_) => DEFAULT_MAX_ENTRIES,
+        };
+        if ttl == 0 {
+            warn!("Agent identity cache DISABLED (RANSOMEYE_INGEST_AGENT_CACHE_TTL_SECS=0)");
+        } else {
+            info!("Agent identity cache: ttl={}s negative_ttl={}s max_entries={}", ttl, negative_ttl, max_entries);
+        }
+        Ok(Self::new(Duration::from_secs(ttl), Duration::from_secs(negative_ttl), max_entries))
+    }
+
+    pub fn new(ttl: Duration, negative_ttl: Duration, max_entries: usize) -> Self {
+        Self { ttl, negative_ttl, max_entries, entries: DashMap::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Resolve (or auto-register) the agent for `component_identity`, from cache when fresh.
+    pub async fn resolve(
+        &self,
+        store: &dyn TelemetryStore,
+        component_identity: &str,
+        source: TelemetrySource,
+    ) -> Result<Uuid, StorageError> {
+        self.resolve_at(store, component_identity, source, Instant::now()).await
+    }
+
+    pub async fn resolve_at(
+        &self,
+        store: &dyn TelemetryStore,
+        component_identity: &str,
+        source: TelemetrySource,
+        now: Instant,
+    ) -> Result<Uuid, StorageError> {
+        if self.ttl.is_zero() {
+            return store.resolve_agent(component_identity, source).await;
+        }
+        let key = (source, component_identity.to_string());
+        if let Some(cached) = self.entries.get(&key) {
+            let age = now.saturating_duration_since(cached.at);
+            match &cached.resolution {
+                Resolution::Agent(agent_id) if age < self.ttl => return Ok(*agent_id),
+                Resolution::Failed(reason) if age < self.negative_ttl => {
+                    return Err(StorageError::Query(format!("agent resolution failed recently: {}", reason)));
+                }
+                _ => {}
+            }
+        }
+
+        match store.resolve_agent(component_identity, source).await {
+            Ok(agent_id) => {
+                self.insert(key, Resolution::Agent(agent_id), now);
+                Ok(agent_id)
+            }
+            Err(e) => {
+                if !self.negative_ttl.is_zero() {
+                    self.insert(key, Resolution::Failed(e.to_string()), now);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    fn insert(&self, key: (TelemetrySource, String), resolution: Resolution, now: Instant) {
+        if self.entries.len() >= self.max_entries && !self.entries.contains_key(&key) {
+            self.evict_expired(now);
+            if self.entries.len() >= self.max_entries {
+                warn!("Agent identity cache full ({} entries); clearing", self.entries.len());
+                self.entries.clear();
+            }
+        }
+        // A failure racing a concurrent successful resolution must not displace its agent_id
+        if matches!(resolution, Resolution::Failed(_)) {
+            let fresh_agent = self.entries.get(&key).is_some_and(|cached| {
+                matches!(cached.resolution, Resolution::Agent(_)) && now.saturating_duration_since(cached.at) < self.ttl
+            });
+            if fresh_agent {
+                return;
+            }
+        }
+        self.entries.insert(key, CachedResolution { resolution, at: now });
+    }
+
+    fn evict_expired(&self, now: Instant) {
+        self.entries.retain(|_, cached| {
+            let age = now.saturating_duration_since(cached.at);
+            match cached.resolution {
+                Resolution::Agent(_) => age < self.ttl,
+                Resolution::Failed(_) => age < self.negative_ttl,
+            }
+        });
+    }
+
+    /// Forget every cached resolution of `component_identity` (enrollment changed its agents row).
+    pub fn invalidate_identity(&self, component_identity: &str) {
+        self.entries.retain(|(_, identity), _| identity != component_identity);
+    }
+
+    /// Forget every cached resolution to `agent_id`.
+    pub fn invalidate_agent(&self, agent_id: Uuid) {
+        self.entries.retain(|_, cached| !matches!(cached.resolution, Resolution::Agent(id) if id == agent_id));
+    }
+}
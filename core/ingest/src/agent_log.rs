@@ -0,0 +1,327 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/agent_log.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Sealed agent log segments - verifies the line hash chain and Ed25519 seal of segments shipped by Linux agents, checks their continuity with the segments already received and spools them for the evidence store
+
+/*
+ * Sealed Agent Log Segments
+ *
+ * Linux agents with AGENT_LOG_SEALING hash-chain every log line they write and ship sealed
+ * segments (POST /agents/log-segments, agent bearer token):
+ *
+ *   genesis   = SHA-256("ransomeye-log-genesis-v1" | boot_id | chain_id)
+ *   hash(n)   = SHA-256("ransomeye-log-line-v1" | hash(n-1) | line n)
+ *   digest    = SHA-256("ransomeye-log-seal-v1" | chain_id | boot_id | segment_index | first_line |
+ *                       line_count | prev_hash | head_hash | sealed_at | segments_dropped_before)
+ *   signature = Ed25519(agent identity key, digest)
+ *
+ * Fields are joined with 0x1f, hashes are lowercase hex. A segment is accepted when its lines
+ * lead from prev_hash to head_hash and the signature verifies under signer_key. Continuity with
+ * the agent's earlier segments of the same chain (agent_log_segments) is then recorded:
+ *
+ *   genesis   segment 0, prev_hash is the chain's genesis
+ *   linked    prev_hash is the head of the stored segment before it
+ *   gap       the segment before it never arrived (dropped on the agent or lost); accepted, so a
+ *             verifier sees exactly which part of the history is missing
+ *
+ * Refused with 409: a segment whose prev_hash contradicts a stored neighbour (a fork), a chain
+ * whose signer key changes, and a different segment under an index already stored. A resent
+ * segment identical to the stored one is accepted again without a new row.
+ *
+ * Accepted segments are spooled (<segment_id>.json) for `reporting import-agent-logs`, which seals
+ * them into the evidence store; `reporting verify-agent-logs` re-verifies an agent's history there.
+ *
+ * Off unless RANSOMEYE_INGEST_AGENT_LOGS is set; needs the Postgres control plane.
+ */
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use crypto::digest::Sha256;
+use crypto::signature::{verify_ed25519, ED25519_PUBLIC_KEY_LEN, ED25519_SIGNATURE_LEN};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+const SEP: &str = "\x1f";
+const GENESIS_DOMAIN: &str = "ransomeye-log-genesis-v1";
+const LINE_DOMAIN: &str = "ransomeye-log-line-v1";
+const SEAL_DOMAIN: &str = "ransomeye-log-seal-v1";
+
+/// Longest chain_id / boot_id accepted
+pub const MAX_ID_CHARS: usize = 64;
+const DEFAULT_MAX_SEGMENT_BYTES: u64 = 4 * 1024 * 1024;
+const MAX_MAX_SEGMENT_BYTES: u64 = 64 * 1024 * 1024;
+const DEFAULT_SPOOL_DIR: &str = "/var/lib/ransomeye/ingest/agent-log-spool";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgentLogConfig {
+    /// Largest request body of a segment upload
+    pub max_segment_bytes: u64,
+    /// Accepted segments, until `reporting import-agent-logs` takes them
+    pub spool_dir: PathBuf,
+}
+
+impl AgentLogConfig {
+    /// RANSOMEYE_INGEST_AGENT_LOGS=true|1 accepts sealed agent log segments (default off);
+    /// RANSOMEYE_INGEST_AGENT_LOG_MAX_SEGMENT_BYTES (default 4 MiB, at most 64 MiB),
+    /// RANSOMEYE_INGEST_AGENT_LOG_SPOOL_DIR (default /var/lib/ransomeye/ingest/agent-log-spool).
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let enabled = std::env::var("RANSOMEYE_INGEST_AGENT_LOGS")
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+        if !enabled {
+            return Ok(None);
+        }
+        let max_segment_bytes = match std::env::var("RANSOMEYE_INGEST_AGENT_LOG_MAX_SEGMENT_BYTES") {
+            Ok(v) => v
+                .parse::<u64>()
+                .ok()
+                .filter(|n| (64 * 1024..=MAX_MAX_SEGMENT_BYTES).contains(n))
+                .ok_or_else(|| {
+                    format!(
+                        "Invalid RANSOMEYE_INGEST_AGENT_LOG_MAX_SEGMENT_BYTES '{}' (expected 65536..={})",
+                        v, MAX_MAX_SEGMENT_BYTES
+                    )
+                })?,
+            Err(_) => DEFAULT_MAX_SEGMENT_BYTES,
+        };
+        Ok(Some(Self {
+            max_segment_bytes,
+            spool_dir: PathBuf::from(
+                std::env::var("RANSOMEYE_INGEST_AGENT_LOG_SPOOL_DIR").unwrap_or_else(|_| DEFAULT_SPOOL_DIR.to_string()),
+            ),
+        }))
+    }
+}
+
+/// A sealed segment as shipped by the agent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct LogSegment {
+    /// Random per agent start
+    pub chain_id: String,
+    /// Kernel boot id of the host
+    pub boot_id: String,
+    /// Position of the segment in its chain (0-based)
+    pub segment_index: u64,
+    /// Chain position of the first line (0-based)
+    pub first_line: u64,
+    /// Hex hash the first line links to: the previous segment's head, or the genesis
+    pub prev_hash: String,
+    /// Hex hash of the last line
+    pub head_hash: String,
+    /// RFC 3339; signed as sent
+    pub sealed_at: String,
+    /// Segments the agent dropped just before this one (its pending queue was full)
+    pub segments_dropped_before: u64,
+    pub lines: Vec<String>,
+    /// Hex Ed25519 public key of the agent identity
+    pub signer_key: String,
+    /// Base64 Ed25519 signature of the seal digest
+    pub signature: String,
+}
+
+/// Genesis hash of a chain
+pub fn genesis_hash(boot_id: &str, chain_id: &str) -> String {
+    hex::encode(Sha256::digest([GENESIS_DOMAIN, boot_id, chain_id].join(SEP).as_bytes()))
+}
+
+/// Hash of a line linked to the previous hash
+pub fn line_hash(prev_hash: &str, line: &str) -> String {
+    hex::encode(Sha256::digest([LINE_DOMAIN, prev_hash, line].join(SEP).as_bytes()))
+}
+
+fn is_sha256_hex(s: &str) -> bool {
+    s.len() == 64 && s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+fn is_id(s: &str) -> bool {
+    !s.is_empty() && s.len() <= MAX_ID_CHARS && s.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+}
+
+impl LogSegment {
+    /// Digest the signature covers; the lines are covered through head_hash
+    pub fn digest(&self) -> [u8; 32] {
+        let fields = [
+            SEAL_DOMAIN.to_string(),
+            self.chain_id.clone(),
+            self.boot_id.clone(),
+            self.segment_index.to_string(),
+            self.first_line.to_string(),
+            self.lines.len().to_string(),
+            self.prev_hash.clone(),
+            self.head_hash.clone(),
+            self.sealed_at.clone(),
+            self.segments_dropped_before.to_string(),
+        ];
+        Sha256::digest(fields.join(SEP).as_bytes())
+    }
+
+    pub fn sealed_at(&self) -> Result<DateTime<Utc>, String> {
+        DateTime::parse_from_rfc3339(&self.sealed_at)
+            .map(|t| t.with_timezone(&Utc))
+            .map_err(|_| format!("sealed_at '{}' is not RFC 3339", self.sealed_at))
+    }
+
+    pub fn signer_key_bytes(&self) -> Result<[u8; ED25519_PUBLIC_KEY_LEN], String> {
+        hex::decode(&self.signer_key)
+            .ok()
+            .and_then(|b| b.try_into().ok())
+            .ok_or_else(|| format!("signer_key is not a hex {}-byte Ed25519 public key", ED25519_PUBLIC_KEY_LEN))
+    }
+
+    pub fn signature_bytes(&self) -> Result<Vec<u8>, String> {
+        general_purpose::STANDARD
+            .decode(&self.signature)
+            .ok()
+            .filter(|s| s.len() == ED25519_SIGNATURE_LEN)
+            .ok_or_else(|| format!("signature is not a base64 {}-byte Ed25519 signature", ED25519_SIGNATURE_LEN))
+    }
+
+    /// Well-formed, lines hash to head_hash and the seal verifies under signer_key.
+    pub fn verify(&self) -> Result<(), String> {
+        if !is_id(&self.chain_id) || !is_id(&self.boot_id) {
+            return Err(format!("chain_id and boot_id must be 1-{} characters of [A-Za-z0-9-]", MAX_ID_CHARS));
+        }
+        if !is_sha256_hex(&self.prev_hash) || !is_sha256_hex(&self.head_hash) {
+            return Err("prev_hash and head_hash must be lowercase hex SHA-256".to_string());
+        }
+        if self.signer_key.len() != 2 * ED25519_PUBLIC_KEY_LEN || self.signer_key.bytes().any(|b| b.is_ascii_uppercase()) {
+            return Err("signer_key must be a lowercase hex Ed25519 public key".to_string());
+        }
+        if self.lines.is_empty() {
+            return Err("segment has no lines".to_string());
+        }
+        self.sealed_at()?;
+        let head = self.lines.iter().fold(self.prev_hash.clone(), |prev, line| line_hash(&prev, line));
+        if head != self.head_hash {
+            return Err("lines do not hash to head_hash (altered, missing or reordered lines)".to_string());
+        }
+        verify_ed25519(&self.signer_key_bytes()?, &self.digest(), &self.signature_bytes()?)
+            .map_err(|_| "seal signature does not verify under signer_key".to_string())
+    }
+}
+
+/// agent_log_segments.continuity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Continuity {
+    Genesis,
+    Linked,
+    Gap,
+}
+
+impl Continuity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Continuity::Genesis => "genesis",
+            Continuity::Linked => "linked",
+            Continuity::Gap => "gap",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "genesis" => Some(Continuity::Genesis),
+            "linked" => Some(Continuity::Linked),
+            "gap" => Some(Continuity::Gap),
+            _ => None,
+        }
+    }
+}
+
+/// A stored segment of the same chain, as far as continuity needs it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredSegment {
+    pub segment_index: u64,
+    pub prev_hash: String,
+    pub head_hash: String,
+    pub signer_key: String,
+}
+
+/// Outcome of checking a verified segment against the stored segments of its chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    /// New segment with this continuity
+    New(Continuity),
+    /// Identical to the stored segment at its index
+    Duplicate,
+}
+
+/// Place `segment` in its chain given the stored segments at index-1, index and index+1 (any may
+/// be missing). Err: fork, signer key change or a different segment under a stored index.
+pub fn place(segment: &LogSegment, neighbours: &[StoredSegment]) -> Result<Placement, String> {
+    if let Some(other) = neighbours.iter().find(|s| s.signer_key != segment.signer_key) {
+        return Err(format!(
+            "signer key changed within chain {} (segment {} was signed by {})",
+            segment.chain_id, other.segment_index, other.signer_key
+        ));
+    }
+    let at = |index: u64| neighbours.iter().find(|s| s.segment_index == index);
+    if let Some(stored) = at(segment.segment_index) {
+        if stored.prev_hash == segment.prev_hash && stored.head_hash == segment.head_hash {
+            return Ok(Placement::Duplicate);
+        }
+        return Err(format!("segment {} of chain {} is already stored with other lines", segment.segment_index, segment.chain_id));
+    }
+    if let Some(next) = segment.segment_index.checked_add(1).and_then(at) {
+        if next.prev_hash != segment.head_hash {
+            return Err(format!("segment {} does not lead to the stored segment {}", segment.segment_index, next.segment_index));
+        }
+    }
+    if segment.segment_index == 0 {
+        if segment.prev_hash != genesis_hash(&segment.boot_id, &segment.chain_id) {
+            return Err("segment 0 does not start at the chain genesis".to_string());
+        }
+        return Ok(Placement::New(Continuity::Genesis));
+    }
+    match at(segment.segment_index - 1) {
+        Some(prev) if prev.head_hash == segment.prev_hash => Ok(Placement::New(Continuity::Linked)),
+        Some(prev) => Err(format!("segment {} does not follow the stored segment {} (fork)", segment.segment_index, prev.segment_index)),
+        None => Ok(Placement::New(Continuity::Gap)),
+    }
+}
+
+/// Response of POST /agents/log-segments
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LogSegmentReceipt {
+    pub segment_id: Uuid,
+    pub chain_id: String,
+    pub segment_index: u64,
+    /// genesis, linked or gap
+    pub continuity: Continuity,
+    /// The segment was already stored
+    pub duplicate: bool,
+}
+
+/// Spooled segment (<segment_id>.json); read by the reporting importer.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpoolEntry {
+    pub segment_id: Uuid,
+    pub agent_id: Uuid,
+    pub agent_component_id: String,
+    pub received_at: DateTime<Utc>,
+    pub continuity: Continuity,
+    pub segment: LogSegment,
+}
+
+fn write_atomic(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    let mut file = fs::File::create(&tmp)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)
+}
+
+pub fn write_spool(spool_dir: &Path, entry: &SpoolEntry) -> std::io::Result<()> {
+    fs::create_dir_all(spool_dir)?;
+    let json = serde_json::to_vec_pretty(entry).map_err(std::io::Error::other)?;
+    write_atomic(&spool_dir.join(format!("{}.json", entry.segment_id)), &json)
+}
+
+/// Remove a spooled segment whose row was not recorded.
+pub fn remove_spool(spool_dir: &Path, segment_id: Uuid) {
+    let _ = fs::remove_file(spool_dir.join(format!("{}.json", segment_id)));
+}
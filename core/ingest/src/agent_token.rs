@@ -0,0 +1,173 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/agent_token.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Signed per-agent bearer tokens (interim ingest authentication until mTLS) - issuance, parsing, and HMAC verification
+
+/*
+ * Agent API Tokens
+ *
+ * Token format: rey1.<token_id>.<agent_id>.<expires_unix>.<mac_b64url>
+ * where mac = HMAC-SHA256(server_key, "rey1.<token_id>.<agent_id>.<expires_unix>").
+ *
+ * The MAC proves the token was issued by this deployment; the DB row (agent_api_tokens)
+ * is authoritative for rotation and revocation. Only SHA-256(token) is ever stored.
+ * Fails-closed on any parse, MAC, or expiry failure.
+ */
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::{DateTime, TimeZone, Utc};
+use crypto::digest::Sha256;
+use crypto::hmac::HmacSha256Key;
+use thiserror::Error;
+use uuid::Uuid;
+
+const TOKEN_PREFIX: &str = "rey1";
+const MIN_KEY_LEN: usize = 32;
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum AgentTokenError {
+    #[error("Missing bearer token")]
+    Missing,
+    #[error("Malformed token: {0}")]
+    Malformed(String),
+    #[error("Token MAC verification failed")]
+    BadMac,
+    #[error("Token expired at {0}")]
+    Expired(DateTime<Utc>),
+    #[error("Token revoked: {0}")]
+    Revoked(String),
+    #[error("Token unknown to token store: {0}")]
+    Unknown(Uuid),
+    #[error("Token not bound to agent: {0}")]
+    AgentMismatch(String),
+    #[error("Token key invalid: {0}")]
+    KeyInvalid(String),
+}
+
+/// Claims carried inside a verified token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgentTokenClaims {
+    pub token_id: Uuid,
+    pub agent_id: Uuid,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Newly issued token (the plaintext is returned to the agent exactly once).
+#[derive(Debug, Clone)]
+pub struct IssuedAgentToken {
+    pub token: String,
+    pub claims: AgentTokenClaims,
+    pub token_sha256: Vec<u8>,
+}
+
+pub struct AgentTokenSigner {
+    key: HmacSha256Key,
+}
+
+impl AgentTokenSigner {
+    pub fn new(key_bytes: &[u8]) -> Result<Self, AgentTokenError> {
+        if key_bytes.len() < MIN_KEY_LEN {
+            return Err(AgentTokenError::KeyInvalid(format!(
+                "token key must be at least {} bytes (got {})",
+                MIN_KEY_LEN,
+                key_bytes.len()
+            )));
+        }
+        Ok(Self {
+            key: HmacSha256Key::new(key_bytes),
+        })
+    }
+
+    /// Load the HMAC key from a file (raw bytes; trailing newline ignored).
+    pub fn from_key_file(path: &str) -> Result<Self, AgentTokenError> {
+        let mut bytes = std::fs::read(path)
+            .map_err(|e| AgentTokenError::KeyInvalid(format!("cannot read {}: {}", path, e)))?;
+        while bytes.last() == Some(&b'\n') || bytes.last() == Some(&b'\r') {
+            bytes.pop();
+        }
+        Self::new(&bytes)
+    }
+
+    pub fn issue(&self, agent_id: Uuid, expires_at: DateTime<Utc>) -> IssuedAgentToken {
+        let claims = AgentTokenClaims {
+            token_id: Uuid::new_v4(),
+            agent_id,
+            expires_at,
+        };
+        let body = signing_input(&claims);
+        let tag = self.key.sign(body.as_bytes());
+        let token = format!("{}.{}", body, URL_SAFE_NO_PAD.encode(tag));
+        let token_sha256 = token_sha256(&token);
+        IssuedAgentToken {
+            token,
+            claims,
+            token_sha256,
+        }
+    }
+
+    /// Verify format, MAC, and expiry. Rotation/revocation are checked against the DB by the caller.
+    pub fn verify(&self, token: &str, now: DateTime<Utc>) -> Result<AgentTokenClaims, AgentTokenError> {
+        let parts: Vec<&str> = token.split('.').collect();
+        if parts.len() != 5 || parts[0] != TOKEN_PREFIX {
+            return Err(AgentTokenError::Malformed("unexpected token structure".to_string()));
+        }
+
+        let mac = URL_SAFE_NO_PAD
+            .decode(parts[4])
+            .map_err(|e| AgentTokenError::Malformed(format!("mac encoding: {}", e)))?;
+        let body_len = token.len() - parts[4].len() - 1;
+        self.key.verify(token[..body_len].as_bytes(), &mac).map_err(|_| AgentTokenError::BadMac)?;
+
+        let token_id = Uuid::parse_str(parts[1])
+            .map_err(|e| AgentTokenError::Malformed(format!("token_id: {}", e)))?;
+        let agent_id = Uuid::parse_str(parts[2])
+            .map_err(|e| AgentTokenError::Malformed(format!("agent_id: {}", e)))?;
+        let expires_unix = parts[3]
+            .parse::<i64>()
+            .map_err(|e| AgentTokenError::Malformed(format!("expiry: {}", e)))?;
+        let expires_at = Utc
+            .timestamp_opt(expires_unix, 0)
+            .single()
+            .ok_or_else(|| AgentTokenError::Malformed("expiry out of range".to_string()))?;
+
+        if expires_at <= now {
+            return Err(AgentTokenError::Expired(expires_at));
+        }
+
+        Ok(AgentTokenClaims {
+            token_id,
+            agent_id,
+            expires_at,
+        })
+    }
+}
+
+/// Extract the token from an `Authorization: Bearer <token>` header value.
+pub fn parse_bearer(header_value: Option<&str>) -> Result<&str, AgentTokenError> {
+    let value = header_value.ok_or(AgentTokenError::Missing)?;
+    let token = value
+        .strip_prefix("Bearer ")
+        .or_else(|| value.strip_prefix("bearer "))
+        .ok_or_else(|| AgentTokenError::Malformed("expected Bearer scheme".to_string()))?
+        .trim();
+    if token.is_empty() {
+        return Err(AgentTokenError::Missing);
+    }
+    Ok(token)
+}
+
+/// SHA-256 digest of the full token string (the only form persisted in the DB).
+pub fn token_sha256(token: &str) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(token.as_bytes());
+    hasher.finalize().to_vec()
+}
+
+fn signing_input(claims: &AgentTokenClaims) -> String {
+    format!(
+        "{}.{}.{}.{}",
+        TOKEN_PREFIX,
+        claims.token_id,
+        claims.agent_id,
+        claims.expires_at.timestamp()
+    )
+}
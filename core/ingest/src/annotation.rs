@@ -0,0 +1,126 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/annotation.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Investigation annotations - annotated subject types (incident, detection, timeline entry), subject and note validation, and the annotation record with its immutable revision history
+
+/*
+ * Investigation Annotations
+ *
+ * Analysts attach notes to what they are investigating (POST /admin/annotations):
+ *
+ *   incident        incident UUID (the incident_id carried by incident-linked rows)
+ *   detection       detection_results.detection_id
+ *   timeline_entry  id of a forensic timeline entry (evidence_id / audit record_id)
+ *
+ * A note is never changed in place. An edit (POST /admin/annotations/edit) appends the next
+ * revision with its author and reason; both tables are append-only in the database, so the
+ * first text, every edit and who made it stay available to forensic exports. An edit names the
+ * revision it replaces, and an edit based on a stale revision is refused rather than silently
+ * overwriting a colleague's note. Annotations need the Postgres control plane; the reporting
+ * pipeline reads the GET /admin/annotations export.
+ */
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Longest note body
+pub const MAX_BODY_BYTES: usize = 16 * 1024;
+const MAX_FIELD_BYTES: usize = 256;
+
+/// annotations.subject_type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AnnotationSubject {
+    Incident,
+    Detection,
+    TimelineEntry,
+}
+
+impl AnnotationSubject {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AnnotationSubject::Incident => "incident",
+            AnnotationSubject::Detection => "detection",
+            AnnotationSubject::TimelineEntry => "timeline_entry",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "incident" => Some(AnnotationSubject::Incident),
+            "detection" => Some(AnnotationSubject::Detection),
+            "timeline_entry" => Some(AnnotationSubject::TimelineEntry),
+            _ => None,
+        }
+    }
+
+    /// Canonical subject id: incidents and detections are UUIDs (lowercase hyphenated),
+    /// timeline entries an opaque id without whitespace.
+    pub fn normalize_id(&self, subject_id: &str) -> Result<String, String> {
+        let subject_id = subject_id.trim();
+        match self {
+            AnnotationSubject::Incident | AnnotationSubject::Detection => Uuid::parse_str(subject_id)
+                .map(|id| id.to_string())
+                .map_err(|_| format!("{} subject_id '{}' is not a UUID", self.as_str(), subject_id)),
+            AnnotationSubject::TimelineEntry => {
+                check_field("subject_id", subject_id)?;
+                if subject_id.chars().any(|c| c.is_whitespace() || c.is_control()) {
+                    return Err("timeline_entry subject_id must not contain whitespace".to_string());
+                }
+                Ok(subject_id.to_string())
+            }
+        }
+    }
+}
+
+/// Author or subject field: non-empty, bounded.
+pub fn check_field(field: &str, value: &str) -> Result<(), String> {
+    if value.trim().is_empty() {
+        return Err(format!("{} is empty", field));
+    }
+    if value.len() > MAX_FIELD_BYTES {
+        return Err(format!("{} exceeds {} bytes", field, MAX_FIELD_BYTES));
+    }
+    Ok(())
+}
+
+/// Note text: non-empty, at most MAX_BODY_BYTES.
+pub fn check_body(body: &str) -> Result<(), String> {
+    if body.trim().is_empty() {
+        return Err("body is empty".to_string());
+    }
+    if body.len() > MAX_BODY_BYTES {
+        return Err(format!("body exceeds {} bytes", MAX_BODY_BYTES));
+    }
+    Ok(())
+}
+
+/// One version of a note; revision 1 is the original text.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AnnotationRevision {
+    pub revision: i32,
+    pub body: String,
+    pub author: String,
+    pub edited_at: DateTime<Utc>,
+    /// Why the note was edited (None for revision 1)
+    pub edit_reason: Option<String>,
+}
+
+/// A note with its full history, as listed by the admin API.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Annotation {
+    pub annotation_id: Uuid,
+    pub subject_type: AnnotationSubject,
+    pub subject_id: String,
+    /// Author of revision 1
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    /// Current revision number, text, author and time
+    pub revision: i32,
+    pub body: String,
+    pub author: String,
+    pub edited_at: DateTime<Utc>,
+    /// Every revision, oldest first
+    pub revisions: Vec<AnnotationRevision>,
+}
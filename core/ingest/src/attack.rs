@@ -0,0 +1,269 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/attack.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: MITRE ATT&CK technique tags for rules and detections - tactics, the technique catalog with the data sources each needs, the tags of built-in ingest detectors and what each sensor type observes
+
+/*
+ * MITRE ATT&CK Tagging
+ *
+ * A tag is a technique ID (T1486, T1053.005) and the tactic it serves in the rule, kept as the
+ * ATT&CK slug (impact, defense-evasion). Tactics are accepted as ID, slug or display name and
+ * normalized, so a tag always compares equal to itself however it was written.
+ *
+ * Detectors built into ingest are tagged here rather than at each call site; insert_detection
+ * fills detection_results.mitre_technique / mitre_tactic from this table. Correlation rules
+ * carry their own tags (KillChainRule.attack).
+ *
+ * The technique catalog lists the techniques the coverage report tracks and the data sources
+ * (event categories) that can reveal each one. A sensor type observes a technique when it
+ * produces one of those categories; the Windows agent's categories come from the Windows event
+ * mapping.
+ */
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::protocol::windows_mapping::WindowsEventMappings;
+
+/// Enterprise ATT&CK tactic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tactic {
+    pub id: &'static str,
+    pub slug: &'static str,
+    pub name: &'static str,
+}
+
+pub const TACTICS: &[Tactic] = &[
+    Tactic { id: "TA0043", slug: "reconnaissance", name: "Reconnaissance" },
+    Tactic { id: "TA0042", slug: "resource-development", name: "Resource Development" },
+    Tactic { id: "TA0001", slug: "initial-access", name: "Initial Access" },
+    Tactic { id: "TA0002", slug: "execution", name: "Execution" },
+    Tactic { id: "TA0003", slug: "persistence", name: "Persistence" },
+    Tactic { id: "TA0004", slug: "privilege-escalation", name: "Privilege Escalation" },
+    Tactic { id: "TA0005", slug: "defense-evasion", name: "Defense Evasion" },
+    Tactic { id: "TA0006", slug: "credential-access", name: "Credential Access" },
+    Tactic { id: "TA0007", slug: "discovery", name: "Discovery" },
+    Tactic { id: "TA0008", slug: "lateral-movement", name: "Lateral Movement" },
+    Tactic { id: "TA0009", slug: "collection", name: "Collection" },
+    Tactic { id: "TA0011", slug: "command-and-control", name: "Command and Control" },
+    Tactic { id: "TA0010", slug: "exfiltration", name: "Exfiltration" },
+    Tactic { id: "TA0040", slug: "impact", name: "Impact" },
+];
+
+/// Tactic by ID, slug or display name (case, spaces and underscores ignored)
+pub fn tactic(name: &str) -> Option<&'static Tactic> {
+    let wanted = name.trim().to_ascii_lowercase().replace([' ', '_'], "-");
+    TACTICS
+        .iter()
+        .find(|t| t.id.eq_ignore_ascii_case(&wanted) || t.slug == wanted || t.name.to_ascii_lowercase().replace(' ', "-") == wanted)
+}
+
+/// T followed by four digits, optionally a sub-technique (.NNN)
+pub fn is_technique_id(id: &str) -> bool {
+    let b = id.as_bytes();
+    let digits = |s: &[u8]| s.iter().all(u8::is_ascii_digit);
+    match b.len() {
+        5 => b[0] == b'T' && digits(&b[1..]),
+        9 => b[0] == b'T' && digits(&b[1..5]) && b[5] == b'.' && digits(&b[6..]),
+        _ => false,
+    }
+}
+
+/// ATT&CK technique a rule or detection is tagged with
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+pub struct AttackTag {
+    /// e.g. T1486, T1053.005
+    pub technique_id: String,
+    /// Tactic slug, e.g. impact
+    pub tactic: String,
+}
+
+impl AttackTag {
+    /// Validated tag with the tactic normalized to its slug
+    pub fn new(technique_id: &str, tactic_name: &str) -> Result<Self, String> {
+        let technique_id = technique_id.trim().to_ascii_uppercase();
+        if !is_technique_id(&technique_id) {
+            return Err(format!("invalid ATT&CK technique id '{}'", technique_id));
+        }
+        let tactic = tactic(tactic_name).ok_or_else(|| format!("unknown ATT&CK tactic '{}'", tactic_name))?;
+        Ok(Self { technique_id, tactic: tactic.slug.to_string() })
+    }
+
+    /// For tags that arrived deserialized: same checks as new(), and the tactic must already be a slug
+    pub fn validate(&self) -> Result<(), String> {
+        let normalized = Self::new(&self.technique_id, &self.tactic)?;
+        if normalized != *self {
+            return Err(format!(
+                "ATT&CK tag {}/{} is not normalized (expected {}/{})",
+                self.technique_id, self.tactic, normalized.technique_id, normalized.tactic
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Event category a technique can be seen in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DataSource {
+    Process,
+    Filesystem,
+    Network,
+    /// DPI flow records
+    NetworkFlow,
+    Registry,
+    Authentication,
+    Service,
+    ScheduledTask,
+    AuditLog,
+}
+
+impl DataSource {
+    /// Agent event_category (Windows mapping categories included)
+    pub fn from_category(category: &str) -> Option<Self> {
+        Some(match category {
+            "process" => DataSource::Process,
+            "filesystem" => DataSource::Filesystem,
+            "network" => DataSource::Network,
+            "registry" => DataSource::Registry,
+            "authentication" => DataSource::Authentication,
+            "service" => DataSource::Service,
+            "scheduled_task" => DataSource::ScheduledTask,
+            "audit_log" => DataSource::AuditLog,
+            _ => return None,
+        })
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DataSource::Process => "process",
+            DataSource::Filesystem => "filesystem",
+            DataSource::Network => "network",
+            DataSource::NetworkFlow => "network_flow",
+            DataSource::Registry => "registry",
+            DataSource::Authentication => "authentication",
+            DataSource::Service => "service",
+            DataSource::ScheduledTask => "scheduled_task",
+            DataSource::AuditLog => "audit_log",
+        }
+    }
+}
+
+/// Technique tracked by the coverage report
+#[derive(Debug, Clone, Copy)]
+pub struct Technique {
+    pub id: &'static str,
+    pub name: &'static str,
+    /// Tactic slug the technique is reported under
+    pub tactic: &'static str,
+    /// Any one of these can reveal it
+    pub data_sources: &'static [DataSource],
+}
+
+use DataSource::*;
+
+/// Techniques common in ransomware operations, from initial access to impact
+pub const TECHNIQUES: &[Technique] = &[
+    Technique { id: "T1190", name: "Exploit Public-Facing Application", tactic: "initial-access", data_sources: &[NetworkFlow] },
+    Technique { id: "T1133", name: "External Remote Services", tactic: "initial-access", data_sources: &[NetworkFlow, Authentication] },
+    Technique { id: "T1078", name: "Valid Accounts", tactic: "initial-access", data_sources: &[Authentication] },
+    Technique { id: "T1059", name: "Command and Scripting Interpreter", tactic: "execution", data_sources: &[Process] },
+    Technique { id: "T1204.002", name: "User Execution: Malicious File", tactic: "execution", data_sources: &[Process, Filesystem] },
+    Technique { id: "T1543.003", name: "Create or Modify System Process: Windows Service", tactic: "persistence", data_sources: &[Service] },
+    Technique { id: "T1053.005", name: "Scheduled Task/Job: Scheduled Task", tactic: "persistence", data_sources: &[ScheduledTask] },
+    Technique { id: "T1547.001", name: "Boot or Logon Autostart Execution: Registry Run Keys", tactic: "persistence", data_sources: &[Registry] },
+    Technique { id: "T1036", name: "Masquerading", tactic: "defense-evasion", data_sources: &[Process, Filesystem] },
+    Technique { id: "T1562", name: "Impair Defenses", tactic: "defense-evasion", data_sources: &[Process, Service, Registry] },
+    Technique { id: "T1070.001", name: "Indicator Removal: Clear Windows Event Logs", tactic: "defense-evasion", data_sources: &[AuditLog] },
+    Technique { id: "T1003", name: "OS Credential Dumping", tactic: "credential-access", data_sources: &[Process] },
+    Technique { id: "T1110", name: "Brute Force", tactic: "credential-access", data_sources: &[Authentication] },
+    Technique { id: "T1046", name: "Network Service Discovery", tactic: "discovery", data_sources: &[Network, NetworkFlow] },
+    Technique { id: "T1083", name: "File and Directory Discovery", tactic: "discovery", data_sources: &[Process, Filesystem] },
+    Technique { id: "T1021", name: "Remote Services", tactic: "lateral-movement", data_sources: &[Network, NetworkFlow, Authentication] },
+    Technique { id: "T1021.001", name: "Remote Services: Remote Desktop Protocol", tactic: "lateral-movement", data_sources: &[NetworkFlow, Authentication] },
+    Technique { id: "T1021.002", name: "Remote Services: SMB/Windows Admin Shares", tactic: "lateral-movement", data_sources: &[Network, NetworkFlow, Authentication] },
+    Technique { id: "T1071", name: "Application Layer Protocol", tactic: "command-and-control", data_sources: &[NetworkFlow] },
+    Technique { id: "T1041", name: "Exfiltration Over C2 Channel", tactic: "exfiltration", data_sources: &[NetworkFlow] },
+    Technique { id: "T1567", name: "Exfiltration Over Web Service", tactic: "exfiltration", data_sources: &[NetworkFlow] },
+    Technique { id: "T1485", name: "Data Destruction", tactic: "impact", data_sources: &[Filesystem] },
+    Technique { id: "T1486", name: "Data Encrypted for Impact", tactic: "impact", data_sources: &[Filesystem] },
+    Technique { id: "T1489", name: "Service Stop", tactic: "impact", data_sources: &[Process, Service] },
+    Technique { id: "T1490", name: "Inhibit System Recovery", tactic: "impact", data_sources: &[Process] },
+    Technique { id: "T1491.001", name: "Defacement: Internal Defacement", tactic: "impact", data_sources: &[Filesystem] },
+];
+
+pub fn technique(id: &str) -> Option<&'static Technique> {
+    TECHNIQUES.iter().find(|t| t.id.eq_ignore_ascii_case(id.trim()))
+}
+
+/// A detector built into ingest and its tag, if any technique fits it
+#[derive(Debug, Clone, Copy)]
+pub struct BuiltinDetector {
+    pub detection_engine: &'static str,
+    pub detection_name: &'static str,
+    /// (technique ID, tactic slug)
+    pub attack: Option<(&'static str, &'static str)>,
+}
+
+pub const BUILTIN_DETECTORS: &[BuiltinDetector] = &[
+    // A forged or altered process event hides what ran on the host
+    BuiltinDetector {
+        detection_engine: "ingest_process_lineage",
+        detection_name: "process_lineage_tampered_event",
+        attack: Some(("T1562", "defense-evasion")),
+    },
+    BuiltinDetector {
+        detection_engine: "ingest_process_lineage",
+        detection_name: "process_lineage_broken_link",
+        attack: Some(("T1562", "defense-evasion")),
+    },
+    // Someone sending as an enrolled agent with a key it never had
+    BuiltinDetector {
+        detection_engine: "ingest_key_pinning",
+        detection_name: "agent_unexpected_signing_key",
+        attack: Some(("T1036", "defense-evasion")),
+    },
+    // Administration traffic between zones the site topology does not connect
+    BuiltinDetector {
+        detection_engine: "ingest_topology",
+        detection_name: "cross_zone_lateral_movement",
+        attack: Some(("T1021", "lateral-movement")),
+    },
+    BuiltinDetector {
+        detection_engine: "ingest_sandbox",
+        detection_name: "sandbox_malicious_verdict",
+        attack: Some(("T1204.002", "execution")),
+    },
+    // Operational findings; cloned hosts and noisy agents are far likelier than an attack
+    BuiltinDetector { detection_engine: "ingest_identity_conflict", detection_name: "duplicate_agent_identity", attack: None },
+    BuiltinDetector { detection_engine: "ingest_rate_budget", detection_name: "ingest_rate_budget_exceeded", attack: None },
+];
+
+/// Tag of a built-in detector (None for detectors not in the table or without a technique)
+pub fn for_detector(detection_engine: &str, detection_name: &str) -> Option<AttackTag> {
+    BUILTIN_DETECTORS
+        .iter()
+        .find(|d| d.detection_engine == detection_engine && d.detection_name == detection_name)
+        .and_then(|d| d.attack)
+        .map(|(technique_id, tactic)| AttackTag::new(technique_id, tactic).expect("built-in detector tags are valid"))
+}
+
+/// agents.agent_type values that send telemetry
+pub const SENSOR_TYPES: &[&str] = &["linux_agent", "windows_agent", "dpi_probe"];
+
+/// Data sources a sensor type produces (empty for unknown types)
+pub fn sensor_data_sources(agent_type: &str) -> BTreeSet<DataSource> {
+    match agent_type {
+        "linux_agent" => [Process, Filesystem, Network].into_iter().collect(),
+        "dpi_probe" => [NetworkFlow].into_iter().collect(),
+        "windows_agent" => WindowsEventMappings::builtin()
+            .ok()
+            .and_then(|m| m.for_version(None).map(|v| v.categories()))
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|c| DataSource::from_category(c))
+            .collect(),
+        _ => BTreeSet::new(),
+    }
+}
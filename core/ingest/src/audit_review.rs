@@ -0,0 +1,275 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/audit_review.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Audit log review attestations - authorized reviewers, link-by-link verification of an immutable_audit_log time range with its root hash, the attestation record and the reviewed/unreviewed coverage of a period
+
+/*
+ * Audit Log Review
+ *
+ * Compliance requires immutable_audit_log to be reviewed periodically. A reviewer named in
+ * RANSOMEYE_AUDIT_REVIEWERS attests (POST /admin/audit-reviews) to having reviewed the entries
+ * created in [range_start, range_end). The range is re-verified before the attestation is
+ * accepted: every entry must name its predecessor (prev_audit_id) and carry
+ * SHA256(previous chain hash || payload_sha256). A broken link refuses the attestation.
+ *
+ * The root hash is the chain hash of the last entry created before range_end. It commits to
+ * that entry and everything before it, so a later change anywhere in the reviewed range no
+ * longer matches the attestation. An empty range attests the chain head at range_end.
+ *
+ * The attestation (reviewer, range, entry count, first and last entry, root hash) is appended to
+ * the chain itself as AUDIT_REVIEW_ATTESTED and is read back from there by GET
+ * /admin/audit-reviews and the coverage report; it has no table of its own to alter.
+ *
+ * range_end must be at least RANGE_SETTLE_SECS in the past: an entry's created_at is taken
+ * inside its transaction, so it can commit shortly after a later timestamp was read.
+ */
+
+use chrono::{DateTime, Utc};
+use crypto::digest::Sha256;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// immutable_audit_log.action of an attestation
+pub const ATTESTATION_ACTION: &str = "AUDIT_REVIEW_ATTESTED";
+/// range_end must be at least this far in the past
+pub const RANGE_SETTLE_SECS: i64 = 60;
+/// Longest reviewer name and notes
+const MAX_REVIEWER_BYTES: usize = 256;
+pub const MAX_NOTES_BYTES: usize = 4096;
+
+/// Reviewers allowed to attest (RANSOMEYE_AUDIT_REVIEWERS); an operator signed in through the
+/// IdP matches by subject or name.
+#[derive(Debug, Clone, Default)]
+pub struct AuditReviewers {
+    names: Vec<String>,
+}
+
+impl AuditReviewers {
+    /// No reviewers configured: attestations are refused.
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(&std::env::var("RANSOMEYE_AUDIT_REVIEWERS").unwrap_or_default())
+            .map_err(|e| format!("RANSOMEYE_AUDIT_REVIEWERS: {}", e))
+    }
+
+    /// Comma-separated reviewer names.
+    pub fn parse(list: &str) -> Result<Self, String> {
+        let mut names = Vec::new();
+        for name in list.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            check_reviewer(name)?;
+            names.push(name.to_string());
+        }
+        Ok(Self { names })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn contains(&self, reviewer: &str) -> bool {
+        self.names.iter().any(|n| n == reviewer)
+    }
+}
+
+pub fn check_reviewer(reviewer: &str) -> Result<(), String> {
+    if reviewer.trim().is_empty() {
+        return Err("reviewer is empty".to_string());
+    }
+    if reviewer.len() > MAX_REVIEWER_BYTES {
+        return Err(format!("reviewer exceeds {} bytes", MAX_REVIEWER_BYTES));
+    }
+    Ok(())
+}
+
+/// Range to attest: start before end, end settled.
+pub fn check_range(start: DateTime<Utc>, end: DateTime<Utc>, now: DateTime<Utc>) -> Result<(), String> {
+    if start >= end {
+        return Err("range_start must be before range_end".to_string());
+    }
+    if end > now - chrono::Duration::seconds(RANGE_SETTLE_SECS) {
+        return Err(format!("range_end must be at least {}s in the past", RANGE_SETTLE_SECS));
+    }
+    Ok(())
+}
+
+/// The chain columns of one immutable_audit_log row.
+#[derive(Debug, Clone)]
+pub struct ChainLink {
+    pub audit_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub prev_audit_id: Option<Uuid>,
+    pub payload_sha256: Vec<u8>,
+    pub chain_hash_sha256: Vec<u8>,
+}
+
+/// Outcome of verifying a range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RangeDigest {
+    pub entry_count: i64,
+    pub first_audit_id: Option<Uuid>,
+    pub last_audit_id: Option<Uuid>,
+    /// Hex chain hash of the last entry before range_end (zeros for an empty log)
+    pub root_hash: String,
+}
+
+/// Verifies the entries of a range in chain order, fed in pages.
+pub struct RangeVerifier {
+    prev: Option<(Uuid, Vec<u8>)>,
+    entry_count: i64,
+    first_audit_id: Option<Uuid>,
+}
+
+impl RangeVerifier {
+    /// `predecessor`: the last entry created before the range (None if the range starts the log).
+    pub fn new(predecessor: Option<&ChainLink>) -> Self {
+        Self {
+            prev: predecessor.map(|p| (p.audit_id, p.chain_hash_sha256.clone())),
+            entry_count: 0,
+            first_audit_id: None,
+        }
+    }
+
+    pub fn push(&mut self, link: &ChainLink) -> Result<(), String> {
+        let expected_prev = self.prev.as_ref().map(|(id, _)| *id);
+        if link.prev_audit_id != expected_prev {
+            return Err(format!(
+                "audit entry {} links to {:?}, expected {:?}",
+                link.audit_id, link.prev_audit_id, expected_prev
+            ));
+        }
+        let mut input = Vec::with_capacity(64);
+        input.extend_from_slice(self.prev.as_ref().map(|(_, h)| h.as_slice()).unwrap_or(&[0u8; 32]));
+        input.extend_from_slice(&link.payload_sha256);
+        if Sha256::digest(&input).as_slice() != link.chain_hash_sha256.as_slice() {
+            return Err(format!("audit entry {} chain hash does not match its predecessor and payload", link.audit_id));
+        }
+        self.first_audit_id.get_or_insert(link.audit_id);
+        self.entry_count += 1;
+        self.prev = Some((link.audit_id, link.chain_hash_sha256.clone()));
+        Ok(())
+    }
+
+    pub fn finish(self) -> RangeDigest {
+        let (last_audit_id, root_hash) = match self.prev {
+            Some((id, hash)) => (Some(id), hex::encode(hash)),
+            None => (None, hex::encode([0u8; 32])),
+        };
+        RangeDigest {
+            entry_count: self.entry_count,
+            first_audit_id: self.first_audit_id,
+            last_audit_id: if self.entry_count > 0 { last_audit_id } else { None },
+            root_hash,
+        }
+    }
+}
+
+/// How the reviewer was authenticated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewerAuth {
+    AdminKey,
+    Oidc,
+}
+
+/// Payload of an AUDIT_REVIEW_ATTESTED audit entry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AttestationPayload {
+    pub reviewer: String,
+    pub authenticated_by: ReviewerAuth,
+    pub range_start: DateTime<Utc>,
+    pub range_end: DateTime<Utc>,
+    pub entry_count: i64,
+    pub first_audit_id: Option<Uuid>,
+    pub last_audit_id: Option<Uuid>,
+    pub root_hash: String,
+    pub notes: Option<String>,
+}
+
+/// An attestation as reported: its payload and the audit entry that records it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AuditReviewAttestation {
+    /// audit_id of the AUDIT_REVIEW_ATTESTED entry
+    pub attestation_id: Uuid,
+    pub attested_at: DateTime<Utc>,
+    pub reviewer: String,
+    pub authenticated_by: ReviewerAuth,
+    pub range_start: DateTime<Utc>,
+    /// Exclusive
+    pub range_end: DateTime<Utc>,
+    /// Entries created in the range
+    pub entry_count: i64,
+    pub first_audit_id: Option<Uuid>,
+    pub last_audit_id: Option<Uuid>,
+    /// Hex chain hash of the last entry before range_end
+    pub root_hash: String,
+    pub notes: Option<String>,
+}
+
+impl AuditReviewAttestation {
+    pub fn from_entry(attestation_id: Uuid, attested_at: DateTime<Utc>, p: AttestationPayload) -> Self {
+        Self {
+            attestation_id,
+            attested_at,
+            reviewer: p.reviewer,
+            authenticated_by: p.authenticated_by,
+            range_start: p.range_start,
+            range_end: p.range_end,
+            entry_count: p.entry_count,
+            first_audit_id: p.first_audit_id,
+            last_audit_id: p.last_audit_id,
+            root_hash: p.root_hash,
+            notes: p.notes,
+        }
+    }
+}
+
+/// Half-open time period [start, end).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ReviewPeriod {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+/// Which parts of a period are covered by attestations.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AuditReviewCoverage {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// Merged attested periods within [from, to)
+    pub reviewed: Vec<ReviewPeriod>,
+    pub unreviewed: Vec<ReviewPeriod>,
+    /// Attestations overlapping the period, oldest first
+    pub attestations: Vec<AuditReviewAttestation>,
+}
+
+/// Coverage of [from, to) by `attestations` (any order).
+pub fn coverage(from: DateTime<Utc>, to: DateTime<Utc>, attestations: Vec<AuditReviewAttestation>) -> AuditReviewCoverage {
+    let mut attestations: Vec<AuditReviewAttestation> =
+        attestations.into_iter().filter(|a| a.range_start < to && a.range_end > from).collect();
+    attestations.sort_by_key(|a| (a.attested_at, a.attestation_id));
+
+    let mut periods: Vec<ReviewPeriod> = attestations
+        .iter()
+        .map(|a| ReviewPeriod { start: a.range_start.max(from), end: a.range_end.min(to) })
+        .collect();
+    periods.sort_by_key(|p| p.start);
+    let mut reviewed: Vec<ReviewPeriod> = Vec::new();
+    for period in periods {
+        match reviewed.last_mut() {
+            Some(last) if period.start <= last.end => last.end = last.end.max(period.end),
+            _ => reviewed.push(period),
+        }
+    }
+
+    let mut unreviewed = Vec::new();
+    let mut cursor = from;
+    for period in &reviewed {
+        if period.start > cursor {
+            unreviewed.push(ReviewPeriod { start: cursor, end: period.start });
+        }
+        cursor = cursor.max(period.end);
+    }
+    if cursor < to {
+        unreviewed.push(ReviewPeriod { start: cursor, end: to });
+    }
+    AuditReviewCoverage { from, to, reviewed, unreviewed, attestations }
+}
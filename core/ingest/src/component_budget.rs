@@ -0,0 +1,263 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/component_budget.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Policy-driven per-component ingest rate budgets - fixed one-minute windows per component instance, quotas published by the orchestrator
+
+/*
+ * Component Rate Budgets
+ *
+ * Quotas are resolved by the policy engine (one events/minute budget per component type) and
+ * published by the orchestrator: full mode through the ingest_component_quotas table (polled
+ * here), lite mode directly to the embedded server. Component types without a quota are not
+ * budgeted.
+ *
+ * Each component instance (type + component_id) gets a fixed 60s window. Events over budget
+ * are rejected, and the first rejection of a window is reported as a violation so the handler
+ * records one detection per component per window instead of one per dropped event.
+ *
+ * Windows live in process memory, or in ingest_rate_windows when several instances share the
+ * budget (RANSOMEYE_INGEST_SHARED_STATE=postgres, see shared_state).
+ */
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use parking_lot::RwLock;
+use serde::Serialize;
+use tokio_postgres::Client;
+use tracing::{info, warn};
+
+use crate::shared_state::PostgresRateWindows;
+
+/// Budget window length; quotas are expressed per minute.
+pub const BUDGET_WINDOW: Duration = Duration::from_secs(60);
+
+/// Default quota refresh interval (RANSOMEYE_INGEST_QUOTA_REFRESH_SECS).
+const DEFAULT_REFRESH_SECS: u64 = 60;
+
+/// Effective quota for one component type (mirrors policy::IngestQuota).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ComponentQuota {
+    pub component_type: String,
+    pub events_per_minute: u64,
+    pub policy_id: String,
+    pub policy_version: String,
+}
+
+/// One component instance over its budget.
+#[derive(Debug, Clone)]
+pub struct BudgetViolation {
+    pub quota: ComponentQuota,
+    pub component_id: String,
+    pub window_start: DateTime<Utc>,
+    /// True only for the first rejected event of the window.
+    pub first_in_window: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum BudgetDecision {
+    Allowed,
+    Exceeded(BudgetViolation),
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BudgetStats {
+    pub quotas: usize,
+    pub tracked_components: usize,
+    pub rejected_events: u64,
+    pub violation_windows: u64,
+}
+
+struct BudgetWindow {
+    start: Instant,
+    started_at: DateTime<Utc>,
+    count: u64,
+    violated: bool,
+}
+
+pub struct ComponentRateBudget {
+    quotas: RwLock<HashMap<String, ComponentQuota>>,
+    windows: DashMap<(String, String), BudgetWindow>,
+    /// Windows shared by every instance (None: `windows` above)
+    shared: Option<PostgresRateWindows>,
+    rejected_events: AtomicU64,
+    violation_windows: AtomicU64,
+}
+
+impl ComponentRateBudget {
+    pub fn new(quotas: Vec<ComponentQuota>) -> Self {
+        let budget = Self {
+            quotas: RwLock::new(HashMap::new()),
+            windows: DashMap::new(),
+            shared: None,
+            rejected_events: AtomicU64::new(0),
+            violation_windows: AtomicU64::new(0),
+        };
+        budget.replace_quotas(quotas);
+        budget
+    }
+
+    /// Count events in windows shared through Postgres instead of process memory.
+    pub fn with_shared_windows(mut self, windows: PostgresRateWindows) -> Self {
+        self.shared = Some(windows);
+        self
+    }
+
+    pub fn is_shared(&self) -> bool {
+        self.shared.is_some()
+    }
+
+    /// Swap in a new quota set. Open windows keep their counts; the new budget applies immediately.
+    pub fn replace_quotas(&self, quotas: Vec<ComponentQuota>) {
+        let map: HashMap<String, ComponentQuota> =
+            quotas.into_iter().map(|q| (q.component_type.clone(), q)).collect();
+        let mut current = self.quotas.write();
+        if *current != map {
+            info!("Ingest component quotas updated: {} component type(s)", map.len());
+            // Windows for types that lost their quota are dropped
+            self.windows.retain(|(component_type, _), _| map.contains_key(component_type));
+            *current = map;
+        }
+    }
+
+    pub fn quotas(&self) -> Vec<ComponentQuota> {
+        let mut quotas: Vec<ComponentQuota> = self.quotas.read().values().cloned().collect();
+        quotas.sort_by(|a, b| a.component_type.cmp(&b.component_type));
+        quotas
+    }
+
+    /// Count one event from `component_id` against its type's budget.
+    pub fn check(&self, component_type: &str, component_id: &str) -> BudgetDecision {
+        self.check_at(component_type, component_id, Instant::now(), Utc::now())
+    }
+
+    /// `check` with explicit clocks (monotonic for windowing, wall clock for reporting).
+    pub fn check_at(&self, component_type: &str, component_id: &str, now: Instant, wall: DateTime<Utc>) -> BudgetDecision {
+        let quota = match self.quotas.read().get(component_type) {
+            Some(q) => q.clone(),
+            None => return BudgetDecision::Allowed,
+        };
+
+        let mut window = self
+            .windows
+            .entry((component_type.to_string(), component_id.to_string()))
+            .or_insert_with(|| BudgetWindow { start: now, started_at: wall, count: 0, violated: false });
+
+        if now.saturating_duration_since(window.start) >= BUDGET_WINDOW {
+            *window = BudgetWindow { start: now, started_at: wall, count: 0, violated: false };
+        }
+
+        if window.count < quota.events_per_minute {
+            window.count += 1;
+            return BudgetDecision::Allowed;
+        }
+
+        let first_in_window = !window.violated;
+        window.violated = true;
+        self.rejected_events.fetch_add(1, Ordering::Relaxed);
+        if first_in_window {
+            self.violation_windows.fetch_add(1, Ordering::Relaxed);
+        }
+        BudgetDecision::Exceeded(BudgetViolation {
+            quota,
+            component_id: component_id.to_string(),
+            window_start: window.started_at,
+            first_in_window,
+        })
+    }
+
+    /// `check` against the shared windows when configured. Err when they cannot be read; the
+    /// caller rejects the event.
+    pub async fn decide(&self, component_type: &str, component_id: &str) -> Result<BudgetDecision, String> {
+        let Some(shared) = &self.shared else {
+            return Ok(self.check(component_type, component_id));
+        };
+        let quota = match self.quotas.read().get(component_type) {
+            Some(q) => q.clone(),
+            None => return Ok(BudgetDecision::Allowed),
+        };
+        let window = shared.count(component_type, component_id, BUDGET_WINDOW).await?;
+        if window.count <= quota.events_per_minute {
+            return Ok(BudgetDecision::Allowed);
+        }
+        // The count keeps rising past the quota, so exactly one instance sees the first rejection
+        let first_in_window = window.count == quota.events_per_minute + 1;
+        self.rejected_events.fetch_add(1, Ordering::Relaxed);
+        if first_in_window {
+            self.violation_windows.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(BudgetDecision::Exceeded(BudgetViolation {
+            quota,
+            component_id: component_id.to_string(),
+            window_start: window.window_start,
+            first_in_window,
+        }))
+    }
+
+    pub fn stats(&self) -> BudgetStats {
+        BudgetStats {
+            quotas: self.quotas.read().len(),
+            tracked_components: self.windows.len(),
+            rejected_events: self.rejected_events.load(Ordering::Relaxed),
+            violation_windows: self.violation_windows.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Replace quotas with the orchestrator-published set in ingest_component_quotas.
+    pub async fn load_from_db(&self, db: &Client) -> Result<usize, String> {
+        let rows = db
+            .query(
+                "SELECT component_type, events_per_minute, policy_id, policy_version FROM ingest_component_quotas",
+                &[],
+            )
+            .await
+            .map_err(|e| format!("Failed to read ingest_component_quotas: {}", e))?;
+
+        let mut quotas = Vec::with_capacity(rows.len());
+        for row in rows {
+            let events_per_minute: i64 = row.get(1);
+            quotas.push(ComponentQuota {
+                component_type: row.get(0),
+                events_per_minute: u64::try_from(events_per_minute)
+                    .map_err(|_| format!("Invalid events_per_minute {} in ingest_component_quotas", events_per_minute))?,
+                policy_id: row.get(2),
+                policy_version: row.get(3),
+            });
+        }
+        let count = quotas.len();
+        self.replace_quotas(quotas);
+        Ok(count)
+    }
+
+    /// Poll ingest_component_quotas so policy changes reach a running server.
+    /// A failed refresh keeps the last loaded quotas.
+    pub fn spawn_refresh(self: &Arc<Self>, db: Arc<Client>) -> Result<tokio::task::JoinHandle<()>, String> {
+        let interval = refresh_interval_from_env()?;
+        let budget = self.clone();
+        Ok(tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                if let Err(e) = budget.load_from_db(&db).await {
+                    warn!("Ingest quota refresh failed (keeping previous quotas): {}", e);
+                }
+            }
+        }))
+    }
+}
+
+fn refresh_interval_from_env() -> Result<Duration, String> {
+    match std::env::var("RANSOMEYE_INGEST_QUOTA_REFRESH_SECS") {
+        Ok(v) => v
+            .parse::<u64>()
+            .ok()
+            .filter(|n| *n > 0)
+            .map(Duration::from_secs)
+            .ok_or_else(|| format!("Invalid RANSOMEYE_INGEST_QUOTA_REFRESH_SECS '{}'", v)),
+        Err(_) => Ok(Duration::from_secs(DEFAULT_REFRESH_SECS)),
+    }
+}
@@ -0,0 +1,43 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/crash_report.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Panic reporting for the ingest server - records a crash as an error_events row (postgres backend) next to the kernel crash dump
+
+use kernel::crash::{CrashReport, CrashReporter};
+
+use crate::storage::{postgres, StorageBackend, StorageConfig};
+
+/// Reporter for `kernel::crash::install`. Runs on the hook's own thread with a private runtime:
+/// the service runtime may be the thing that panicked. SQLite deployments keep the dump only.
+pub fn reporter() -> CrashReporter {
+    Box::new(|report: &CrashReport| {
+        let cfg = StorageConfig::from_env().map_err(|e| e.to_string())?;
+        if cfg.backend != StorageBackend::Postgres {
+            return Err("storage backend is not postgres; crash dump only".to_string());
+        }
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| format!("crash reporter runtime: {e}"))?;
+        rt.block_on(async {
+            let client = postgres::connect_from_env().await.map_err(|e| e.to_string())?;
+            let context = serde_json::json!({
+                "service": report.service,
+                "pid": report.pid,
+                "thread": report.thread,
+                "location": report.location,
+                "dump_path": report.dump_path.as_ref().map(|p| p.display().to_string()),
+            });
+            client
+                .execute(
+                    r#"
+                    INSERT INTO error_events (observed_at, severity, error_type, error_message, stacktrace, context_json)
+                    VALUES (NOW(), 'critical', 'panic', $1, $2, $3)
+                    "#,
+                    &[&report.message, &report.backtrace, &context],
+                )
+                .await
+                .map(|_| ())
+                .map_err(|e| format!("Failed to insert error_events row: {e}"))
+        })
+    })
+}
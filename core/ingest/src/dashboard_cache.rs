@@ -0,0 +1,278 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/dashboard_cache.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: In-process TTL cache of the dashboard's aggregate queries (detections per severity, events per minute, fleet summary) with single-flight loading, invalidation on detection and enrollment writes, and hit/miss counters
+
+/*
+ * Dashboard Cache
+ *
+ * Every open dashboard polls the same three aggregates. Each is computed at most once per
+ * RANSOMEYE_INGEST_DASHBOARD_CACHE_TTL_SECS on this instance and served from memory in between;
+ * concurrent requests for a stale aggregate wait for a single query instead of each issuing
+ * their own. Failed queries are not cached.
+ *
+ * Writes made through this instance invalidate what they change: a committed detection drops
+ * the severity counts, an enrollment drops the fleet summary. Events per minute changes with
+ * every accepted event and is left to the TTL, as are writes by other instances and services
+ * (the correlation engine, orchestrator decommissioning), so the TTL bounds staleness.
+ *
+ * A load that races an invalidation is returned to its callers but not cached, so a result read
+ * before a write never outlives it. A TTL of 0 disables caching.
+ */
+
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::Mutex as AsyncMutex;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::storage::{EventRate, FleetSummary, SeverityCount, StorageError, TelemetryStore};
+
+const DEFAULT_TTL_SECS: u64 = 15;
+/// Detections counted per severity
+pub const SEVERITY_WINDOW_HOURS: i64 = 24;
+/// Minutes of events per minute
+pub const EVENT_RATE_WINDOW_MINUTES: i64 = 60;
+/// An agent seen within this window counts as reporting
+pub const REPORTING_WINDOW_MINUTES: i64 = 15;
+
+/// Unsuppressed detections of the last 24 hours per severity.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct DetectionsBySeverity {
+    /// When the counts were read
+    pub as_of: DateTime<Utc>,
+    pub window_hours: i64,
+    pub severities: Vec<SeverityCount>,
+}
+
+/// Raw events received per minute over the last hour.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct EventsPerMinute {
+    pub as_of: DateTime<Utc>,
+    pub window_minutes: i64,
+    pub minutes: Vec<EventRate>,
+}
+
+/// Agent counts of the fleet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct FleetOverview {
+    pub as_of: DateTime<Utc>,
+    pub reporting_window_minutes: i64,
+    pub fleet: FleetSummary,
+}
+
+/// Cache counters of one aggregate since start.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct QueryCacheStats {
+    pub query: String,
+    /// Served from memory, including requests that waited for another request's load
+    pub hits: u64,
+    /// Loaded from the store
+    pub misses: u64,
+    pub invalidations: u64,
+    /// Age of the cached result (none: nothing cached)
+    pub age_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct DashboardCacheStats {
+    /// 0: caching disabled
+    pub ttl_secs: u64,
+    pub queries: Vec<QueryCacheStats>,
+}
+
+struct Entry<T> {
+    value: T,
+    at: Instant,
+}
+
+/// One cached aggregate.
+struct Slot<T> {
+    name: &'static str,
+    entry: Mutex<Option<Entry<T>>>,
+    /// Bumped by every invalidation; a load that saw another generation is not cached
+    generation: AtomicU64,
+    /// Single flight: one load at a time
+    loading: AsyncMutex<()>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    invalidations: AtomicU64,
+}
+
+impl<T: Clone> Slot<T> {
+    fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            entry: Mutex::new(None),
+            generation: AtomicU64::new(0),
+            loading: AsyncMutex::new(()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            invalidations: AtomicU64::new(0),
+        }
+    }
+
+    fn fresh(&self, ttl: Duration, now: Instant) -> Option<T> {
+        let entry = self.entry.lock().unwrap_or_else(|e| e.into_inner());
+        entry.as_ref().filter(|e| now.saturating_duration_since(e.at) < ttl).map(|e| e.value.clone())
+    }
+
+    async fn get<F, Fut>(&self, ttl: Duration, now: Instant, load: F) -> Result<T, StorageError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, StorageError>>,
+    {
+        if ttl.is_zero() {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return load().await;
+        }
+        if let Some(value) = self.fresh(ttl, now) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(value);
+        }
+        let _loading = self.loading.lock().await;
+        // Another request may have loaded it while this one waited
+        if let Some(value) = self.fresh(ttl, now) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(value);
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let generation = self.generation.load(Ordering::SeqCst);
+        let value = load().await?;
+        let mut entry = self.entry.lock().unwrap_or_else(|e| e.into_inner());
+        if self.generation.load(Ordering::SeqCst) == generation {
+            *entry = Some(Entry { value: value.clone(), at: now });
+        }
+        Ok(value)
+    }
+
+    fn invalidate(&self) {
+        let mut entry = self.entry.lock().unwrap_or_else(|e| e.into_inner());
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        *entry = None;
+        self.invalidations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn stats(&self, now: Instant) -> QueryCacheStats {
+        let age_secs = self
+            .entry
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .map(|e| now.saturating_duration_since(e.at).as_secs());
+        QueryCacheStats {
+            query: self.name.to_string(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            invalidations: self.invalidations.load(Ordering::Relaxed),
+            age_secs,
+        }
+    }
+}
+
+pub struct DashboardCache {
+    /// Zero disables caching (every request queries the store)
+    ttl: Duration,
+    detections: Slot<DetectionsBySeverity>,
+    events: Slot<EventsPerMinute>,
+    fleet: Slot<FleetOverview>,
+}
+
+impl DashboardCache {
+    pub fn from_env() -> Result<Self, String> {
+        let ttl = match std::env::var("RANSOMEYE_INGEST_DASHBOARD_CACHE_TTL_SECS") {
+            Ok(v) => v
+                .parse::<u64>()
+                .map_err(|_| format!("Invalid RANSOMEYE_INGEST_DASHBOARD_CACHE_TTL_SECS '{}' (expected seconds)", v))?,
+            Err(_) => DEFAULT_TTL_SECS,
+        };
+        if ttl == 0 {
+            warn!("Dashboard cache DISABLED (RANSOMEYE_INGEST_DASHBOARD_CACHE_TTL_SECS=0)");
+        } else {
+            info!("Dashboard cache: ttl={}s", ttl);
+        }
+        Ok(Self::new(Duration::from_secs(ttl)))
+    }
+
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            detections: Slot::new("detections_by_severity"),
+            events: Slot::new("events_per_minute"),
+            fleet: Slot::new("fleet_summary"),
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    pub async fn detections_by_severity(&self, store: &dyn TelemetryStore) -> Result<DetectionsBySeverity, StorageError> {
+        self.detections_by_severity_at(store, Instant::now()).await
+    }
+
+    pub async fn detections_by_severity_at(
+        &self,
+        store: &dyn TelemetryStore,
+        now: Instant,
+    ) -> Result<DetectionsBySeverity, StorageError> {
+        self.detections
+            .get(self.ttl, now, || async {
+                let as_of = Utc::now();
+                let severities =
+                    store.detection_severity_counts(as_of - chrono::Duration::hours(SEVERITY_WINDOW_HOURS)).await?;
+                Ok(DetectionsBySeverity { as_of, window_hours: SEVERITY_WINDOW_HOURS, severities })
+            })
+            .await
+    }
+
+    pub async fn events_per_minute(&self, store: &dyn TelemetryStore) -> Result<EventsPerMinute, StorageError> {
+        self.events_per_minute_at(store, Instant::now()).await
+    }
+
+    pub async fn events_per_minute_at(&self, store: &dyn TelemetryStore, now: Instant) -> Result<EventsPerMinute, StorageError> {
+        self.events
+            .get(self.ttl, now, || async {
+                let as_of = Utc::now();
+                let minutes = store.event_rate(as_of - chrono::Duration::minutes(EVENT_RATE_WINDOW_MINUTES)).await?;
+                Ok(EventsPerMinute { as_of, window_minutes: EVENT_RATE_WINDOW_MINUTES, minutes })
+            })
+            .await
+    }
+
+    pub async fn fleet_summary(&self, store: &dyn TelemetryStore) -> Result<FleetOverview, StorageError> {
+        self.fleet_summary_at(store, Instant::now()).await
+    }
+
+    pub async fn fleet_summary_at(&self, store: &dyn TelemetryStore, now: Instant) -> Result<FleetOverview, StorageError> {
+        self.fleet
+            .get(self.ttl, now, || async {
+                let as_of = Utc::now();
+                let fleet = store.fleet_summary(as_of - chrono::Duration::minutes(REPORTING_WINDOW_MINUTES)).await?;
+                Ok(FleetOverview { as_of, reporting_window_minutes: REPORTING_WINDOW_MINUTES, fleet })
+            })
+            .await
+    }
+
+    /// A detection was committed.
+    pub fn invalidate_detections(&self) {
+        self.detections.invalidate();
+    }
+
+    /// An agent was enrolled or its agents row changed.
+    pub fn invalidate_fleet(&self) {
+        self.fleet.invalidate();
+    }
+
+    pub fn stats(&self) -> DashboardCacheStats {
+        let now = Instant::now();
+        DashboardCacheStats {
+            ttl_secs: self.ttl.as_secs(),
+            queries: vec![self.detections.stats(now), self.events.stats(now), self.fleet.stats(now)],
+        }
+    }
+}
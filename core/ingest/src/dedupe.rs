@@ -18,7 +18,7 @@ use std::sync::Arc;
 use std::time::{Duration, Instant};
 use dashmap::DashMap;
 use parking_lot::RwLock;
-use sha2::{Sha256, Digest};
+use crypto::digest::Sha256;
 use tracing::{warn, debug};
 use chrono::Utc;
 
@@ -0,0 +1,360 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/disk_quota.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Disk quotas of the ingest evidence spools - configured budgets per spool, upload admission (507 with Retry-After), periodic usage scans with warning alerts (health + disk.quota webhook) and purge of the oldest spooled items not linked to an active legal hold
+
+/*
+ * Evidence Spool Quotas
+ *
+ * Packet captures, memory images, sandbox reports/samples and sealed agent logs are spooled on
+ * the ingest host until the reporting importer seals them into the evidence store. A stalled
+ * importer or a burst of acquisitions would otherwise fill the disk. Each spool may get a budget
+ * (RANSOMEYE_INGEST_DISK_QUOTAS=name:budget_mb[:policy],...) with an overflow policy:
+ *
+ *   reject        uploads that would exceed the budget get 507 Insufficient Storage (Retry-After)
+ *   purge_oldest  uploads are accepted; at the budget the scan deletes the oldest spooled items
+ *                 down to the warning level, never one linked to an active legal hold (through
+ *                 its row's incident_id) or a capture or memory acquisition still in progress.
+ *                 If the holds cannot be read nothing is purged. When only held items are left,
+ *                 uploads are refused as under reject.
+ *
+ * Every scan reports level changes (ok / warning / exceeded) in the log and as disk.quota
+ * webhooks, and purges as disk.quota webhooks listing the purged ids. The current usage is the
+ * disk_quota subsystem of the health report. Purged items are lost before they were sealed.
+ */
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    extract::State,
+    http::{header, HeaderValue, Request, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use governor::quota::{self, DirectoryQuota, PurgePlan, QuotaLevel, QuotaManager, QuotaTransition, DEFAULT_WARN_PERCENT};
+use health::{HealthReport, HealthStatus};
+use tokio_postgres::Client;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::webhooks::{self, WebhookEvent};
+
+pub const PCAP_SPOOL: &str = "pcap_spool";
+pub const MEMORY_SPOOL: &str = "memory_spool";
+pub const SANDBOX_SPOOL: &str = "sandbox_spool";
+pub const AGENT_LOG_SPOOL: &str = "agent_log_spool";
+
+/// Spools a quota may name.
+pub const SPOOLS: &[&str] = &[PCAP_SPOOL, MEMORY_SPOOL, SANDBOX_SPOOL, AGENT_LOG_SPOOL];
+
+const DEFAULT_SCAN_SECS: u64 = 60;
+
+/// Spooled items of `ids` that must not be purged, per spool. Agent log segments carry no
+/// incident or entity, so no hold reaches them.
+const PCAP_KEEP: &str = r#"
+    SELECT capture_id FROM pcap_captures
+    WHERE capture_id = ANY($1)
+      AND (status = 'capturing'
+           OR incident_id IN (SELECT subject_id FROM legal_holds WHERE subject_type = 'incident' AND released_at IS NULL))
+"#;
+const MEMORY_KEEP: &str = r#"
+    SELECT acquisition_id FROM memory_acquisitions
+    WHERE acquisition_id = ANY($1)
+      AND (status IN ('requested', 'acquiring')
+           OR incident_id IN (SELECT subject_id FROM legal_holds WHERE subject_type = 'incident' AND released_at IS NULL))
+"#;
+const SANDBOX_KEEP: &str = r#"
+    SELECT submission_id FROM sandbox_submissions
+    WHERE submission_id = ANY($1)
+      AND incident_id IN (SELECT subject_id FROM legal_holds WHERE subject_type = 'incident' AND released_at IS NULL)
+"#;
+
+#[derive(Debug, Clone)]
+pub struct DiskQuotaConfig {
+    pub quotas: Vec<quota::QuotaSpec>,
+    pub warn_percent: u8,
+    pub scan_interval: Duration,
+}
+
+impl DiskQuotaConfig {
+    pub fn from_env() -> Result<Self, String> {
+        let quotas = match std::env::var("RANSOMEYE_INGEST_DISK_QUOTAS") {
+            Ok(v) => quota::parse_specs(&v).map_err(|e| format!("RANSOMEYE_INGEST_DISK_QUOTAS: {}", e))?,
+            Err(_) => Vec::new(),
+        };
+        if let Some(unknown) = quotas.iter().find(|q| !SPOOLS.contains(&q.name.as_str())) {
+            return Err(format!(
+                "RANSOMEYE_INGEST_DISK_QUOTAS: unknown spool '{}' (expected one of {})",
+                unknown.name,
+                SPOOLS.join(", ")
+            ));
+        }
+        let warn_percent = match std::env::var("RANSOMEYE_INGEST_DISK_QUOTA_WARN_PERCENT") {
+            Ok(v) => v
+                .parse::<u8>()
+                .ok()
+                .filter(|p| (1..=99).contains(p))
+                .ok_or_else(|| format!("Invalid RANSOMEYE_INGEST_DISK_QUOTA_WARN_PERCENT '{}' (1-99)", v))?,
+            Err(_) => DEFAULT_WARN_PERCENT,
+        };
+        let scan_interval = match std::env::var("RANSOMEYE_INGEST_DISK_QUOTA_SCAN_SECS") {
+            Ok(v) => v
+                .parse::<u64>()
+                .ok()
+                .filter(|n| *n > 0)
+                .map(Duration::from_secs)
+                .ok_or_else(|| format!("Invalid RANSOMEYE_INGEST_DISK_QUOTA_SCAN_SECS '{}'", v))?,
+            Err(_) => Duration::from_secs(DEFAULT_SCAN_SECS),
+        };
+        Ok(Self { quotas, warn_percent, scan_interval })
+    }
+}
+
+/// Quotas of this instance's spools. Spools without a quota are not limited.
+pub struct DiskQuotas {
+    manager: QuotaManager,
+    scan_interval: Duration,
+}
+
+impl DiskQuotas {
+    /// `spools` maps each spool name to its directory, None when the feature is off. A quota on a
+    /// spool that is off is a configuration error (FAIL-CLOSED).
+    pub fn new(cfg: &DiskQuotaConfig, spools: &[(&str, Option<&Path>)]) -> Result<Self, String> {
+        let mut quotas = Vec::with_capacity(cfg.quotas.len());
+        for spec in &cfg.quotas {
+            let path = spools
+                .iter()
+                .find(|(name, _)| *name == spec.name)
+                .and_then(|(_, path)| *path)
+                .ok_or_else(|| format!("FAIL-CLOSED: disk quota on {} but that spool is not enabled", spec.name))?;
+            quotas.push(DirectoryQuota {
+                name: spec.name.clone(),
+                path: path.to_path_buf(),
+                budget_bytes: spec.budget_bytes,
+                policy: spec.policy,
+            });
+        }
+        let manager = QuotaManager::new(quotas, cfg.warn_percent).map_err(|e| format!("FAIL-CLOSED: {}", e))?;
+        Ok(Self { manager, scan_interval: cfg.scan_interval })
+    }
+
+    /// No quotas (every upload admitted).
+    pub fn disabled() -> Self {
+        Self {
+            manager: QuotaManager::new(Vec::new(), DEFAULT_WARN_PERCENT).expect("empty quota set is valid"),
+            scan_interval: Duration::from_secs(DEFAULT_SCAN_SECS),
+        }
+    }
+
+    pub fn manager(&self) -> &QuotaManager {
+        &self.manager
+    }
+
+    /// Upload admission: 507 when `bytes` more would overflow a spool that refuses writes.
+    pub fn admit(&self, spool: &str, bytes: u64) -> Result<(), StatusCode> {
+        self.manager.admit(spool, bytes).map_err(|e| {
+            warn!("Upload refused: {}", e);
+            StatusCode::INSUFFICIENT_STORAGE
+        })
+    }
+
+    /// Count an upload written to the spool (until the next scan measures it).
+    pub fn record_write(&self, spool: &str, bytes: u64) {
+        self.manager.record_write(spool, bytes);
+    }
+
+    /// Scan, alert and purge every `scan_interval`. Holds are read through `db`.
+    pub fn spawn_scan(self: &Arc<Self>, db: Arc<Client>, instance_id: String) -> tokio::task::JoinHandle<()> {
+        let quotas = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(quotas.scan_interval);
+            loop {
+                ticker.tick().await;
+                quotas.scan_once(&db, &instance_id).await;
+            }
+        })
+    }
+
+    /// One scan: measure, report level changes, then purge spools at their budget.
+    pub async fn scan_once(self: &Arc<Self>, db: &Client, instance_id: &str) {
+        let quotas = self.clone();
+        let transitions = match tokio::task::spawn_blocking(move || quotas.manager.scan()).await {
+            Ok(t) => t,
+            Err(e) => {
+                error!("Disk quota scan task failed: {}", e);
+                return;
+            }
+        };
+        for t in &transitions {
+            log_transition(t);
+            let data = serde_json::json!({
+                "instance_id": instance_id,
+                "spool": t.name,
+                "level": t.to.as_str(),
+                "previous_level": t.from.as_str(),
+                "used_bytes": t.used_bytes,
+                "budget_bytes": t.budget_bytes,
+            });
+            enqueue(db, data).await;
+        }
+
+        for (quota, used_bytes, target_bytes) in self.manager.purge_due() {
+            let plan = match self.purge(db, &quota, used_bytes, target_bytes).await {
+                Ok(plan) => plan,
+                Err(e) => {
+                    error!("FAIL-CLOSED: Disk quota purge of {} skipped: {}", quota.name, e);
+                    continue;
+                }
+            };
+            self.manager.record_purge(&quota.name, &plan);
+            let remaining = used_bytes.saturating_sub(plan.bytes);
+            if remaining >= quota.budget_bytes {
+                error!(
+                    "Disk quota {} still exceeded after purge ({} of {} bytes; {} held item(s) kept); uploads refused",
+                    quota.name, remaining, quota.budget_bytes, plan.held_skipped
+                );
+            }
+            if plan.entries.is_empty() {
+                continue;
+            }
+            warn!(
+                "Disk quota {} purged {} spooled item(s), {} bytes ({} held item(s) kept) | {}",
+                quota.name,
+                plan.entries.len(),
+                plan.bytes,
+                plan.held_skipped,
+                plan.entries.iter().map(|e| e.key.as_str()).collect::<Vec<_>>().join(",")
+            );
+            let data = serde_json::json!({
+                "instance_id": instance_id,
+                "spool": quota.name,
+                "level": QuotaLevel::of(remaining, quota.budget_bytes, self.manager.warn_percent()).as_str(),
+                "used_bytes": remaining,
+                "budget_bytes": quota.budget_bytes,
+                "purged": plan.entries.iter().map(|e| e.key.clone()).collect::<Vec<_>>(),
+                "purged_bytes": plan.bytes,
+                "held_kept": plan.held_skipped,
+            });
+            enqueue(db, data).await;
+        }
+    }
+
+    /// Delete the oldest unheld items of one spool. Only items named by a UUID (the spool's
+    /// capture, acquisition, submission or segment id) are candidates.
+    async fn purge(
+        &self,
+        db: &Client,
+        quota: &DirectoryQuota,
+        used_bytes: u64,
+        target_bytes: u64,
+    ) -> Result<PurgePlan, String> {
+        let dir = quota.path.clone();
+        let entries = tokio::task::spawn_blocking(move || quota::spool_entries(&dir))
+            .await
+            .map_err(|e| format!("spool listing task failed: {}", e))?
+            .map_err(|e| format!("cannot list {}: {}", quota.path.display(), e))?;
+
+        let ids: Vec<Uuid> = entries.iter().filter_map(|e| Uuid::parse_str(&e.key).ok()).collect();
+        let keep = keep_ids(db, &quota.name, &ids).await?;
+        let plan = quota::plan_purge(
+            &entries,
+            |e| Uuid::parse_str(&e.key).map_or(true, |id| keep.contains(&id)),
+            used_bytes,
+            target_bytes,
+        );
+
+        let doomed = plan.entries.clone();
+        let path = quota.path.clone();
+        tokio::task::spawn_blocking(move || {
+            for entry in &doomed {
+                quota::remove_spool_entry(entry).map_err(|e| format!("cannot purge {} from {}: {}", entry.key, path.display(), e))?;
+            }
+            Ok::<(), String>(())
+        })
+        .await
+        .map_err(|e| format!("purge task failed: {}", e))??;
+        Ok(plan)
+    }
+
+    /// The disk_quota subsystem: degraded at a warning, unhealthy once a spool is at its budget.
+    pub fn push_health(&self, report: &mut HealthReport) {
+        let usage = self.manager.usage();
+        if usage.is_empty() {
+            report.push_subsystem("disk_quota", HealthStatus::Healthy, Some("no quotas".to_string()));
+            return;
+        }
+        let mut status = HealthStatus::Healthy;
+        let mut details: Vec<String> = Vec::new();
+        for u in &usage {
+            match u.level {
+                QuotaLevel::Ok => {}
+                QuotaLevel::Warning => status = status.worst(HealthStatus::Degraded),
+                QuotaLevel::Exceeded => status = status.worst(HealthStatus::Unhealthy),
+            }
+            if u.level != QuotaLevel::Ok {
+                details.push(format!(
+                    "{} {} ({}% of {} MiB, {})",
+                    u.name,
+                    u.level.as_str(),
+                    u.used_bytes.saturating_mul(100) / u.budget_bytes.max(1),
+                    u.budget_bytes / (1024 * 1024),
+                    u.policy.as_str()
+                ));
+            }
+            report.set_metric(format!("disk_quota_{}_used_bytes", u.name), u.used_bytes as f64);
+            report.set_metric(format!("disk_quota_{}_budget_bytes", u.name), u.budget_bytes as f64);
+            report.set_metric(format!("disk_quota_{}_rejected", u.name), u.rejected_writes as f64);
+            report.set_metric(format!("disk_quota_{}_purged", u.name), u.purged_entries as f64);
+        }
+        let detail = if details.is_empty() { None } else { Some(details.join("; ")) };
+        report.push_subsystem("disk_quota", status, detail);
+    }
+}
+
+async fn keep_ids(db: &Client, spool: &str, ids: &[Uuid]) -> Result<HashSet<Uuid>, String> {
+    let sql = match spool {
+        PCAP_SPOOL => PCAP_KEEP,
+        MEMORY_SPOOL => MEMORY_KEEP,
+        SANDBOX_SPOOL => SANDBOX_KEEP,
+        _ => return Ok(HashSet::new()),
+    };
+    if ids.is_empty() {
+        return Ok(HashSet::new());
+    }
+    let rows = db
+        .query(sql, &[&ids])
+        .await
+        .map_err(|e| format!("cannot read legal holds for {}: {}", spool, e))?;
+    Ok(rows.iter().map(|r| r.get::<_, Uuid>(0)).collect())
+}
+
+fn log_transition(t: &QuotaTransition) {
+    let pct = t.used_bytes.saturating_mul(100) / t.budget_bytes.max(1);
+    match t.to {
+        QuotaLevel::Ok => info!("Disk quota {} back to ok ({}% of budget)", t.name, pct),
+        QuotaLevel::Warning => warn!("Disk quota {} warning: {}% of {} bytes used", t.name, pct, t.budget_bytes),
+        QuotaLevel::Exceeded => error!("Disk quota {} exceeded: {} of {} bytes used", t.name, t.used_bytes, t.budget_bytes),
+    }
+}
+
+async fn enqueue(db: &Client, data: serde_json::Value) {
+    let event = WebhookEvent::new(webhooks::DISK_QUOTA, data);
+    if let Err(e) = webhooks::enqueue(db, &event).await {
+        warn!("Failed to enqueue {} webhook: {}", webhooks::DISK_QUOTA, e);
+    }
+}
+
+/// Router layer on the upload routes: a 507 tells the uploader to retry after the next scan.
+pub async fn retry_after(State(quotas): State<Arc<DiskQuotas>>, req: Request<Body>, next: Next) -> Response {
+    let mut response = next.run(req).await;
+    if response.status() == StatusCode::INSUFFICIENT_STORAGE {
+        response
+            .headers_mut()
+            .entry(header::RETRY_AFTER)
+            .or_insert(HeaderValue::from(quotas.scan_interval.as_secs()));
+    }
+    response
+}
@@ -0,0 +1,264 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/drop_accounting.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Dropped-event accounting - agent-reported drop counters applied from agent_stats reports and ingest-side rejections counted per agent, summed per day into telemetry_drops_daily
+
+/*
+ * Dropped-Event Accounting
+ *
+ * Telemetry is lost in two places. Both are summed per agent, UTC day, origin and reason in
+ * telemetry_drops_daily, so completeness can be read per agent per day:
+ *
+ *   agent   agent_stats reports carry cumulative counters per reason for the agent's current run
+ *           (run_id). The last value per (agent, run, reason) is kept in agent_drop_counters and
+ *           only the increase is added, in the unit of work of the report itself - a duplicate,
+ *           replayed or out-of-order report adds nothing.
+ *   ingest  requests of an authenticated agent that ingest refuses (rate budget, validation,
+ *           residency, key pin, storage failure). Counted in memory and flushed every
+ *           RANSOMEYE_INGEST_DROP_FLUSH_SECS; a failed flush keeps the counts for the next one.
+ *
+ * Refusals without an authenticated agent (auth disabled) cannot be attributed to an agent; they
+ * only appear in the process totals of GET /admin/drops/ingest.
+ */
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use parking_lot::Mutex;
+use serde::Serialize;
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::http_agent_auth::AuthenticatedAgent;
+use crate::protocol::signed_event::LinuxAgentStats;
+use crate::storage::{DropCount, DropOrigin, StorageError, StorageTx, TelemetryStore};
+
+/// Reasons accepted from one agent report; more is treated as a malformed report.
+const MAX_AGENT_REASONS: usize = 16;
+const MAX_REASON_LEN: usize = 32;
+const MAX_RUN_ID_LEN: usize = 64;
+
+fn env_u64(key: &str, default_value: u64) -> Result<u64, String> {
+    match std::env::var(key) {
+        Ok(v) => v
+            .parse::<u64>()
+            .ok()
+            .filter(|n| *n > 0)
+            .ok_or_else(|| format!("Invalid {} '{}' (expected integer > 0)", key, v)),
+        Err(_) => Ok(default_value),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DropAccountingConfig {
+    /// How often ingest-side counts are written to telemetry_drops_daily
+    pub flush_interval: Duration,
+    /// agent_drop_counters rows not advanced for this long (ended runs) are purged
+    pub counter_retention: Duration,
+}
+
+impl DropAccountingConfig {
+    pub fn from_env() -> Result<Self, String> {
+        Ok(Self {
+            flush_interval: Duration::from_secs(env_u64("RANSOMEYE_INGEST_DROP_FLUSH_SECS", 60)?),
+            counter_retention: Duration::from_secs(env_u64("RANSOMEYE_INGEST_DROP_COUNTER_RETAIN_SECS", 7 * 86_400)?),
+        })
+    }
+}
+
+/// Drop reason recorded for an ingest response; None when the event was accepted (or quarantined).
+pub fn ingest_drop_reason(status: StatusCode) -> Option<&'static str> {
+    match status.as_u16() {
+        200..=299 => None,
+        400 => Some("invalid"),
+        401 => Some("unauthorized"),
+        403 => Some("forbidden"),
+        413 => Some("too_large"),
+        421 => Some("residency"),
+        429 => Some("rate_limited"),
+        500..=599 => Some("server_error"),
+        _ => Some("rejected"),
+    }
+}
+
+fn valid_reason(reason: &str) -> bool {
+    !reason.is_empty()
+        && reason.len() <= MAX_REASON_LEN
+        && reason.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_')
+}
+
+/// Add the increase of an agent_stats report's cumulative counters to the day of the report.
+/// Reports without counters (older agents) add nothing; malformed counters are skipped.
+/// Returns the number of newly counted drops.
+pub async fn apply_agent_report(
+    tx: &mut dyn StorageTx,
+    agent_id: Uuid,
+    observed_at: DateTime<Utc>,
+    stats: &LinuxAgentStats,
+) -> Result<u64, StorageError> {
+    let (Some(run_id), Some(counters)) = (stats.run_id.as_deref(), stats.dropped_by_reason.as_ref()) else {
+        return Ok(0);
+    };
+    if run_id.is_empty() || run_id.len() > MAX_RUN_ID_LEN || counters.len() > MAX_AGENT_REASONS {
+        warn!("Ignoring malformed agent drop report | agent_id={} | run_id_len={} | reasons={}", agent_id, run_id.len(), counters.len());
+        return Ok(0);
+    }
+
+    let mut drops = Vec::new();
+    for (reason, cumulative) in counters {
+        if !valid_reason(reason) {
+            warn!("Ignoring agent drop counter with invalid reason | agent_id={} | reason={:?}", agent_id, reason);
+            continue;
+        }
+        let dropped = tx.advance_drop_counter(agent_id, run_id, reason, *cumulative).await?;
+        if dropped > 0 {
+            drops.push(DropCount {
+                agent_id,
+                day: observed_at.date_naive(),
+                origin: DropOrigin::Agent,
+                reason: reason.clone(),
+                dropped,
+            });
+        }
+    }
+    tx.add_drops(&drops).await?;
+    Ok(drops.iter().map(|d| d.dropped).sum())
+}
+
+/// Ingest-side drop totals of this process.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct IngestDropStats {
+    pub since: DateTime<Utc>,
+    /// Refused requests per reason since start (attributed or not)
+    pub by_reason: BTreeMap<String, u64>,
+    /// Refusals without an authenticated agent (not in telemetry_drops_daily)
+    pub unattributed: u64,
+    /// Attributed refusals not yet flushed to telemetry_drops_daily
+    pub pending_flush: u64,
+}
+
+/// In-memory ingest-side drop counts, flushed to telemetry_drops_daily.
+pub struct IngestDropLedger {
+    since: DateTime<Utc>,
+    pending: Mutex<HashMap<(Uuid, NaiveDate, String), u64>>,
+    totals: Mutex<BTreeMap<String, u64>>,
+    unattributed: AtomicU64,
+}
+
+impl Default for IngestDropLedger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IngestDropLedger {
+    pub fn new() -> Self {
+        Self {
+            since: Utc::now(),
+            pending: Mutex::new(HashMap::new()),
+            totals: Mutex::new(BTreeMap::new()),
+            unattributed: AtomicU64::new(0),
+        }
+    }
+
+    pub fn record(&self, agent_id: Option<Uuid>, reason: &str, at: DateTime<Utc>) {
+        *self.totals.lock().entry(reason.to_string()).or_default() += 1;
+        match agent_id {
+            Some(agent_id) => *self.pending.lock().entry((agent_id, at.date_naive(), reason.to_string())).or_default() += 1,
+            None => {
+                self.unattributed.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Remove and return the counts not yet flushed.
+    pub fn take(&self) -> Vec<DropCount> {
+        self.pending
+            .lock()
+            .drain()
+            .map(|((agent_id, day, reason), dropped)| DropCount { agent_id, day, origin: DropOrigin::Ingest, reason, dropped })
+            .collect()
+    }
+
+    /// Put back counts whose flush failed; they are merged with anything recorded since.
+    pub fn restore(&self, drops: Vec<DropCount>) {
+        let mut pending = self.pending.lock();
+        for d in drops {
+            *pending.entry((d.agent_id, d.day, d.reason)).or_default() += d.dropped;
+        }
+    }
+
+    pub fn stats(&self) -> IngestDropStats {
+        IngestDropStats {
+            since: self.since,
+            by_reason: self.totals.lock().clone(),
+            unattributed: self.unattributed.load(Ordering::Relaxed),
+            pending_flush: self.pending.lock().values().sum(),
+        }
+    }
+
+    /// Write pending counts in one unit of work; on failure they stay pending.
+    pub async fn flush(&self, store: &dyn TelemetryStore) -> Result<u64, StorageError> {
+        let drops = self.take();
+        if drops.is_empty() {
+            return Ok(0);
+        }
+        let total = drops.iter().map(|d| d.dropped).sum();
+        let result = async {
+            let mut tx = store.begin().await?;
+            if let Err(e) = tx.add_drops(&drops).await {
+                tx.rollback().await;
+                return Err(e);
+            }
+            tx.commit().await
+        }
+        .await;
+        match result {
+            Ok(()) => Ok(total),
+            Err(e) => {
+                self.restore(drops);
+                Err(e)
+            }
+        }
+    }
+
+    /// Periodic flush, plus purge of agent drop counters of ended runs.
+    pub fn spawn_flush(self: Arc<Self>, store: Arc<dyn TelemetryStore>, cfg: DropAccountingConfig) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut tick = tokio::time::interval(cfg.flush_interval);
+            tick.tick().await;
+            loop {
+                tick.tick().await;
+                match self.flush(store.as_ref()).await {
+                    Ok(0) => {}
+                    Ok(n) => info!("Flushed {} ingest-side drop(s) to telemetry_drops_daily", n),
+                    Err(e) => error!("Failed to flush ingest drop counts (kept for next flush): {}", e),
+                }
+                let before = Utc::now() - chrono::Duration::from_std(cfg.counter_retention).unwrap_or(chrono::Duration::days(7));
+                if let Err(e) = store.purge_drop_counters(before).await {
+                    warn!("Failed to purge agent drop counters: {}", e);
+                }
+            }
+        })
+    }
+}
+
+/// Router layer on the ingest routes (inside token auth): counts refused requests per agent.
+pub async fn count_ingest_drops(State(ledger): State<Arc<IngestDropLedger>>, req: Request<Body>, next: Next) -> Response {
+    let agent_id = req.extensions().get::<AuthenticatedAgent>().map(|a| a.agent_id);
+    let response = next.run(req).await;
+    if let Some(reason) = ingest_drop_reason(response.status()) {
+        ledger.record(agent_id, reason, Utc::now());
+    }
+    response
+}
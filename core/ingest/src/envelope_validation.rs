@@ -0,0 +1,197 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/envelope_validation.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Machine-readable 400 bodies for signed events that break the envelope contract - field path, category, expected type or constraint and a remediation hint, returned to the sending agent
+
+/*
+ * Envelope Validation Errors
+ *
+ * A signed event can be valid JSON and still be unusable: a missing data field, a string where a
+ * number belongs, a timestamp without an offset. Instead of a bare 400 the ingest endpoints answer
+ *
+ *   {"error":"envelope_validation_failed","category":"wrong_type","field":"envelope.data.pid",
+ *    "expected":"u64","message":"invalid type: string \"4242\", expected u64",
+ *    "hint":"Send envelope.data.pid as u64"}
+ *
+ * so the agent can log and count the failure without access to the ingest logs. The hot path
+ * keeps its single borrowed parse: only a parse that already failed is repeated with path
+ * tracking to find the offending field.
+ */
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+pub const ERROR_CODE: &str = "envelope_validation_failed";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidationCategory {
+    /// Not JSON, or truncated
+    MalformedJson,
+    /// A required field is absent or empty
+    MissingField,
+    /// A field has the wrong JSON type
+    WrongType,
+    /// Right type, but the value breaks a constraint (format, range, known version)
+    InvalidValue,
+}
+
+impl ValidationCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ValidationCategory::MalformedJson => "malformed_json",
+            ValidationCategory::MissingField => "missing_field",
+            ValidationCategory::WrongType => "wrong_type",
+            ValidationCategory::InvalidValue => "invalid_value",
+        }
+    }
+}
+
+/// 400 body for a signed event that breaks the envelope contract.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct ValidationError {
+    /// Always `envelope_validation_failed`
+    pub error: &'static str,
+    pub category: ValidationCategory,
+    /// Dotted path from the request body, e.g. `envelope.data.process_data.pid`
+    pub field: String,
+    /// Expected type or constraint
+    pub expected: String,
+    pub message: String,
+    pub hint: String,
+}
+
+impl ValidationError {
+    pub fn new(category: ValidationCategory, field: &str, expected: &str, message: impl Into<String>) -> Self {
+        Self {
+            error: ERROR_CODE,
+            category,
+            field: field.to_string(),
+            expected: expected.to_string(),
+            message: message.into(),
+            hint: hint(category, field, expected),
+        }
+    }
+
+    /// Required header field present but empty.
+    pub fn empty(field: &str) -> Self {
+        Self::new(ValidationCategory::MissingField, field, "non-empty string", format!("{} is empty", field))
+    }
+
+    fn from_json(prefix: &str, err: serde_path_to_error::Error<serde_json::Error>) -> Self {
+        let path = join(prefix, &err.path().to_string());
+        let inner = err.into_inner();
+        let message = strip_position(&inner.to_string());
+        if matches!(inner.classify(), serde_json::error::Category::Syntax | serde_json::error::Category::Eof | serde_json::error::Category::Io) {
+            return Self::new(ValidationCategory::MalformedJson, &path, "JSON object", message);
+        }
+        if let Some(name) = message.strip_prefix("missing field `").and_then(|m| m.strip_suffix('`')) {
+            return Self::new(ValidationCategory::MissingField, &join(&path, name), "present", message);
+        }
+        let expected = message.rsplit_once(", expected ").map(|(_, e)| e.to_string()).unwrap_or_default();
+        let category = if message.starts_with("invalid type:") {
+            ValidationCategory::WrongType
+        } else {
+            ValidationCategory::InvalidValue
+        };
+        Self::new(category, &path, &expected, message)
+    }
+}
+
+/// Parse `json` (found at `prefix` in the request body). The borrowed single parse is tried first;
+/// only if it fails is the input parsed again with path tracking to name the failing field.
+pub fn from_str<'de, T: Deserialize<'de>>(prefix: &str, json: &'de str) -> Result<T, ValidationError> {
+    serde_json::from_str(json).map_err(|_| {
+        match serde_path_to_error::deserialize::<_, T>(&mut serde_json::Deserializer::from_str(json)) {
+            Err(e) => ValidationError::from_json(prefix, e),
+            // Unreachable for a deterministic Deserialize; keep the request refused regardless
+            Ok(_) => ValidationError::new(ValidationCategory::InvalidValue, prefix, "", "rejected by the envelope parser"),
+        }
+    })
+}
+
+/// Byte variant of `from_str` for the request body.
+pub fn from_slice<'de, T: Deserialize<'de>>(prefix: &str, json: &'de [u8]) -> Result<T, ValidationError> {
+    serde_json::from_slice(json).map_err(|_| {
+        match serde_path_to_error::deserialize::<_, T>(&mut serde_json::Deserializer::from_slice(json)) {
+            Err(e) => ValidationError::from_json(prefix, e),
+            Ok(_) => ValidationError::new(ValidationCategory::InvalidValue, prefix, "", "rejected by the envelope parser"),
+        }
+    })
+}
+
+/// Error of an ingest endpoint: a bare status, or a 400 with a validation body.
+#[derive(Debug)]
+pub enum IngestRejection {
+    Status(StatusCode),
+    Invalid(ValidationError),
+}
+
+impl From<StatusCode> for IngestRejection {
+    fn from(status: StatusCode) -> Self {
+        IngestRejection::Status(status)
+    }
+}
+
+impl From<ValidationError> for IngestRejection {
+    fn from(err: ValidationError) -> Self {
+        IngestRejection::Invalid(err)
+    }
+}
+
+impl IntoResponse for IngestRejection {
+    fn into_response(self) -> Response {
+        match self {
+            IngestRejection::Status(status) => status.into_response(),
+            IngestRejection::Invalid(err) => (StatusCode::BAD_REQUEST, Json(err)).into_response(),
+        }
+    }
+}
+
+fn join(prefix: &str, path: &str) -> String {
+    match (prefix.is_empty(), path.is_empty() || path == ".") {
+        (_, true) => prefix.to_string(),
+        (true, false) => path.to_string(),
+        (false, false) => format!("{}.{}", prefix, path),
+    }
+}
+
+/// serde_json appends " at line L column C"; the field path says more to an agent.
+fn strip_position(message: &str) -> String {
+    match message.rsplit_once(" at line ") {
+        Some((head, tail)) if tail.contains(" column ") => head.to_string(),
+        _ => message.to_string(),
+    }
+}
+
+/// Remediation for the fields agents get wrong most, else a generic one per category.
+fn hint(category: ValidationCategory, field: &str, expected: &str) -> String {
+    let name = field.rsplit('.').next().unwrap_or(field);
+    let specific = match name {
+        "timestamp" => Some("Send an RFC 3339 timestamp with an offset, e.g. 2026-10-16T12:00:00Z"),
+        "event_id" => Some("Send a UUID, e.g. 3f1c2b9e-6a4d-4e8f-9b0a-1c2d3e4f5a6b; it is the message_id of the event"),
+        "signature" => Some("Send the base64 Ed25519 signature of payload_hash"),
+        "payload_hash" => Some("Send the SHA-256 hex of the canonical envelope bytes"),
+        "signer_id" => Some("Send the identifier of the signing key"),
+        "component_id" => Some("Send the component identity the agent enrolled with"),
+        "container_id" => Some("Send the full 64-character hex container id, or leave container unset for host activity"),
+        "schema_version" => Some("Send a schema_version this ingest instance has a field mapping for, or upgrade ingest"),
+        "host_inventory" => Some("Check the host_inventory report against the envelope schema (event_schema_v1.json)"),
+        _ => None,
+    };
+    if let Some(hint) = specific {
+        return hint.to_string();
+    }
+    match category {
+        ValidationCategory::MalformedJson => "Send one complete JSON object per request".to_string(),
+        ValidationCategory::MissingField => format!("Add {} to the event", field),
+        ValidationCategory::WrongType if !expected.is_empty() => format!("Send {} as {}", field, expected),
+        ValidationCategory::WrongType | ValidationCategory::InvalidValue => {
+            format!("Check {} against the envelope schema (event_schema_v1.json)", field)
+        }
+    }
+}
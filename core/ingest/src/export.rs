@@ -0,0 +1,364 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/export.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Bulk export of normalized events for external analytics - resumable cursor tokens carrying the session filters, keyset batches from normalized_events, NDJSON lines and export rate limiting
+
+/*
+ * Data Export
+ *
+ * GET /admin/export/events streams normalized_events as NDJSON (http_export_admin). An export
+ * session is fixed when it starts: time window [since, until) on normalized_at (until defaults
+ * to, and is capped at, the session start), optional event kinds and tenant (agents.tenant_id).
+ *
+ * Rows are served in (normalized_at, normalized_event_id) order. Every event line carries the
+ * cursor that resumes right after it; the cursor encodes the session id, its filters and that
+ * position, so a client that lost the connection passes the cursor of the last line it
+ * processed and gets exactly the rows that follow, with the same filters. A response ends with
+ * an `end` line (next_cursor null once the window is exhausted); a stream cut without one was
+ * interrupted.
+ *
+ *   {"type":"event","cursor":"...","event":{...}}
+ *   {"type":"end","next_cursor":"..."|null,"exported":n}
+ *
+ * Exports are limited per instance: RANSOMEYE_INGEST_EXPORT_MAX_CONCURRENT streams at once and
+ * RANSOMEYE_INGEST_EXPORT_REQUESTS_PER_MINUTE requests per minute (429 beyond either).
+ */
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use tokio_postgres::Client;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Rows per keyset query
+pub const EXPORT_BATCH: i64 = 1000;
+pub const DEFAULT_EXPORT_LIMIT: u64 = 100_000;
+pub const MAX_EXPORT_LIMIT: u64 = 1_000_000;
+/// Event kinds accepted in one `event_kind` filter
+pub const MAX_EVENT_KINDS: usize = 64;
+const CURSOR_VERSION: u8 = 1;
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportConfig {
+    pub max_concurrent: usize,
+    pub requests_per_minute: u64,
+}
+
+fn env_u64(key: &str, default_value: u64) -> Result<u64, String> {
+    match std::env::var(key) {
+        Ok(v) => v
+            .parse::<u64>()
+            .ok()
+            .filter(|n| *n > 0)
+            .ok_or_else(|| format!("Invalid {} '{}' (expected integer > 0)", key, v)),
+        Err(_) => Ok(default_value),
+    }
+}
+
+impl ExportConfig {
+    pub fn from_env() -> Result<Self, String> {
+        Ok(Self {
+            max_concurrent: env_u64("RANSOMEYE_INGEST_EXPORT_MAX_CONCURRENT", 2)? as usize,
+            requests_per_minute: env_u64("RANSOMEYE_INGEST_EXPORT_REQUESTS_PER_MINUTE", 30)?,
+        })
+    }
+}
+
+/// Filters of an export session; fixed when the session starts and carried in every cursor.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportFilter {
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    /// Empty: every kind
+    pub event_kinds: Vec<String>,
+    pub tenant: Option<String>,
+}
+
+/// Position in an export session (see module docs).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportCursor {
+    #[serde(rename = "v")]
+    pub version: u8,
+    pub session_id: Uuid,
+    pub filter: ExportFilter,
+    /// Last row served: (normalized_at, normalized_event_id); None before the first row
+    pub after: Option<(DateTime<Utc>, Uuid)>,
+}
+
+impl ExportCursor {
+    pub fn new(filter: ExportFilter) -> Self {
+        Self { version: CURSOR_VERSION, session_id: Uuid::new_v4(), filter, after: None }
+    }
+
+    /// The same session, positioned after one row.
+    pub fn after(&self, normalized_at: DateTime<Utc>, normalized_event_id: Uuid) -> Self {
+        Self { after: Some((normalized_at, normalized_event_id)), ..self.clone() }
+    }
+
+    pub fn encode(&self) -> String {
+        // Serializing plain data cannot fail
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn decode(token: &str) -> Result<Self, String> {
+        let cursor: ExportCursor = URL_SAFE_NO_PAD
+            .decode(token.as_bytes())
+            .ok()
+            .and_then(|b| serde_json::from_slice(&b).ok())
+            .ok_or_else(|| "invalid export cursor".to_string())?;
+        if cursor.version != CURSOR_VERSION {
+            return Err(format!("unsupported export cursor version {}", cursor.version));
+        }
+        Ok(cursor)
+    }
+}
+
+/// Query parameters of GET /admin/export/events.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ExportParams {
+    /// RFC 3339, inclusive (new sessions)
+    pub since: Option<DateTime<Utc>>,
+    /// RFC 3339, exclusive; default and maximum: now (new sessions)
+    pub until: Option<DateTime<Utc>>,
+    /// Comma-separated event kinds (new sessions)
+    pub event_kind: Option<String>,
+    pub tenant: Option<String>,
+    /// Resume an existing session; excludes the filter parameters
+    pub cursor: Option<String>,
+    /// Event lines in this response (default DEFAULT_EXPORT_LIMIT)
+    pub limit: Option<u64>,
+    /// Operator starting the session (audited; required for new sessions)
+    pub requested_by: Option<String>,
+}
+
+/// Where a request starts: a new session or a resumed one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportStart {
+    pub cursor: ExportCursor,
+    pub new_session: bool,
+    pub limit: u64,
+}
+
+impl ExportParams {
+    /// Validate parameters against the current time. Err is a 400 reason.
+    pub fn start(&self, now: DateTime<Utc>) -> Result<ExportStart, String> {
+        let limit = self.limit.unwrap_or(DEFAULT_EXPORT_LIMIT);
+        if !(1..=MAX_EXPORT_LIMIT).contains(&limit) {
+            return Err(format!("limit must be between 1 and {}", MAX_EXPORT_LIMIT));
+        }
+        if let Some(token) = &self.cursor {
+            if self.since.is_some() || self.until.is_some() || self.event_kind.is_some() || self.tenant.is_some() {
+                return Err("cursor carries the session filters; omit since, until, event_kind and tenant".to_string());
+            }
+            return Ok(ExportStart { cursor: ExportCursor::decode(token)?, new_session: false, limit });
+        }
+
+        if self.requested_by.as_deref().is_none_or(|r| r.trim().is_empty()) {
+            return Err("requested_by is required to start an export".to_string());
+        }
+        let since = self.since.ok_or("since is required to start an export")?;
+        let until = self.until.map_or(now, |u| u.min(now));
+        if since >= until {
+            return Err("since must be before until (and before now)".to_string());
+        }
+        let event_kinds: Vec<String> = self
+            .event_kind
+            .as_deref()
+            .unwrap_or("")
+            .split(',')
+            .map(str::trim)
+            .filter(|k| !k.is_empty())
+            .map(str::to_string)
+            .collect();
+        if event_kinds.len() > MAX_EVENT_KINDS {
+            return Err(format!("at most {} event kinds per export", MAX_EVENT_KINDS));
+        }
+        let tenant = self.tenant.as_deref().map(str::trim).filter(|t| !t.is_empty()).map(str::to_string);
+        let filter = ExportFilter { since, until, event_kinds, tenant };
+        Ok(ExportStart { cursor: ExportCursor::new(filter), new_session: true, limit })
+    }
+}
+
+/// One exported normalized event.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ExportedEvent {
+    pub normalized_event_id: Uuid,
+    pub raw_event_id: Uuid,
+    pub normalized_at: DateTime<Utc>,
+    pub observed_at: Option<DateTime<Utc>>,
+    pub source_type: String,
+    pub source_agent_id: Option<Uuid>,
+    pub tenant_id: Option<String>,
+    pub event_kind: String,
+    pub event_subkind: Option<String>,
+    pub severity: String,
+    #[schema(value_type = Option<Object>)]
+    pub attributes: Option<JsonValue>,
+}
+
+/// One NDJSON line of an export response.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExportLine {
+    Event { cursor: String, event: Box<ExportedEvent> },
+    End { next_cursor: Option<String>, exported: u64 },
+}
+
+impl ExportLine {
+    pub fn to_ndjson(&self) -> String {
+        let mut line = serde_json::to_string(self).unwrap_or_default();
+        line.push('\n');
+        line
+    }
+}
+
+/// Next rows of the session after its cursor position.
+pub async fn fetch_batch(db: &Client, cursor: &ExportCursor, limit: i64) -> Result<Vec<ExportedEvent>, String> {
+    let f = &cursor.filter;
+    let (after_at, after_id) = match cursor.after {
+        Some((at, id)) => (Some(at), Some(id)),
+        None => (None, None),
+    };
+    let rows = db
+        .query(
+            r#"
+            SELECT n.normalized_event_id, n.raw_event_id, n.normalized_at, n.observed_at,
+                   n.source_type::text, n.source_agent_id, a.tenant_id, n.event_kind,
+                   n.event_subkind, n.severity::text, n.attributes
+            FROM normalized_events n
+            LEFT JOIN agents a ON a.agent_id = n.source_agent_id
+            WHERE n.normalized_at >= $1 AND n.normalized_at < $2
+              AND (cardinality($3::text[]) = 0 OR n.event_kind = ANY($3::text[]))
+              AND ($4::text IS NULL OR a.tenant_id = $4::text)
+              AND ($5::timestamptz IS NULL OR (n.normalized_at, n.normalized_event_id) > ($5::timestamptz, $6::uuid))
+            ORDER BY n.normalized_at, n.normalized_event_id
+            LIMIT $7
+            "#,
+            &[&f.since, &f.until, &f.event_kinds, &f.tenant, &after_at, &after_id, &limit],
+        )
+        .await
+        .map_err(|e| format!("Failed to read normalized_events: {}", e))?;
+    Ok(rows
+        .iter()
+        .map(|r| ExportedEvent {
+            normalized_event_id: r.get(0),
+            raw_event_id: r.get(1),
+            normalized_at: r.get(2),
+            observed_at: r.get(3),
+            source_type: r.get(4),
+            source_agent_id: r.get(5),
+            tenant_id: r.get(6),
+            event_kind: r.get(7),
+            event_subkind: r.get(8),
+            severity: r.get(9),
+            attributes: r.get(10),
+        })
+        .collect())
+}
+
+/// Why an export was refused (429).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExportRefusal {
+    TooManyConcurrent,
+    RateLimited { retry_after_secs: u64 },
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ExportStats {
+    pub active_streams: usize,
+    pub sessions_started: u64,
+    pub rows_exported: u64,
+    pub refused: u64,
+}
+
+/// Concurrency and request-rate limits for exports on this instance.
+pub struct ExportLimiter {
+    cfg: ExportConfig,
+    active: Arc<AtomicUsize>,
+    /// (window start, requests in window)
+    window: Mutex<(Instant, u64)>,
+    sessions_started: AtomicU64,
+    rows_exported: Arc<AtomicU64>,
+    refused: AtomicU64,
+}
+
+/// Held by a running export stream; releases its slot on drop.
+pub struct ExportPermit {
+    active: Arc<AtomicUsize>,
+    rows_exported: Arc<AtomicU64>,
+}
+
+impl ExportPermit {
+    pub fn count_rows(&self, rows: u64) {
+        self.rows_exported.fetch_add(rows, Ordering::Relaxed);
+    }
+}
+
+impl Drop for ExportPermit {
+    fn drop(&mut self) {
+        self.active.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+impl ExportLimiter {
+    pub fn new(cfg: ExportConfig) -> Self {
+        Self {
+            cfg,
+            active: Arc::new(AtomicUsize::new(0)),
+            window: Mutex::new((Instant::now(), 0)),
+            sessions_started: AtomicU64::new(0),
+            rows_exported: Arc::new(AtomicU64::new(0)),
+            refused: AtomicU64::new(0),
+        }
+    }
+
+    pub fn acquire(&self) -> Result<ExportPermit, ExportRefusal> {
+        self.acquire_at(Instant::now())
+    }
+
+    /// `acquire` with an explicit clock.
+    pub fn acquire_at(&self, now: Instant) -> Result<ExportPermit, ExportRefusal> {
+        let refuse = |r: ExportRefusal| {
+            self.refused.fetch_add(1, Ordering::Relaxed);
+            Err(r)
+        };
+        {
+            let mut window = self.window.lock();
+            if now.saturating_duration_since(window.0) >= RATE_WINDOW {
+                *window = (now, 0);
+            }
+            if window.1 >= self.cfg.requests_per_minute {
+                let retry = RATE_WINDOW.saturating_sub(now.saturating_duration_since(window.0));
+                return refuse(ExportRefusal::RateLimited { retry_after_secs: retry.as_secs().max(1) });
+            }
+            window.1 += 1;
+        }
+        let admitted = self
+            .active
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| (n < self.cfg.max_concurrent).then_some(n + 1));
+        if admitted.is_err() {
+            return refuse(ExportRefusal::TooManyConcurrent);
+        }
+        Ok(ExportPermit { active: self.active.clone(), rows_exported: self.rows_exported.clone() })
+    }
+
+    pub fn session_started(&self) {
+        self.sessions_started.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> ExportStats {
+        ExportStats {
+            active_streams: self.active.load(Ordering::Acquire),
+            sessions_started: self.sessions_started.load(Ordering::Relaxed),
+            rows_exported: self.rows_exported.load(Ordering::Relaxed),
+            refused: self.refused.load(Ordering::Relaxed),
+        }
+    }
+}
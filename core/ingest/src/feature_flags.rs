@@ -0,0 +1,233 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/feature_flags.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Runtime feature flags - DB-backed switches for risky capabilities with per-tenant overrides, cached in memory and refreshed from Postgres so a change reaches running services without a redeploy
+
+/*
+ * Runtime Feature Flags
+ *
+ * Cargo features decide what is built; environment variables decide what a deployment may do.
+ * Feature flags switch a built and configured capability off (or back on) while services run.
+ *
+ *   feature_flags (flag_key, tenant_id NULL)      global value of a flag
+ *   feature_flags (flag_key, tenant_id 'acme')    override for the agents of one tenant
+ *
+ * The effective value for a tenant is its override, else the global value, else the flag's
+ * default. Every flag defaults to on, so a deployment without rows behaves as before; the
+ * environment switch (RANSOMEYE_INGEST_MEMORY_ACQUISITION, ...) still has to allow the capability.
+ *
+ * Services consult the in-memory snapshot only. It is loaded at startup (FAIL-CLOSED if the table
+ * cannot be read), reloaded every RANSOMEYE_INGEST_FEATURE_FLAG_REFRESH_SECS and right after a
+ * change through this instance's admin API; a failed reload keeps the last snapshot. Changes are
+ * made through POST /admin/feature-flags (and /clear) and audited.
+ */
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use serde::Serialize;
+use tokio_postgres::Client;
+use tracing::warn;
+use utoipa::ToSchema;
+
+/// Default snapshot refresh interval (RANSOMEYE_INGEST_FEATURE_FLAG_REFRESH_SECS).
+const DEFAULT_REFRESH_SECS: u64 = 15;
+/// Longest tenant_id accepted in an override
+pub const MAX_TENANT_CHARS: usize = 128;
+
+/// Capabilities that can be switched at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Flag {
+    /// Operator-requested agent memory images (requests and agent claims)
+    MemoryAcquisition,
+    /// Triggered DPI packet captures (probe claims)
+    PcapCapture,
+    /// Sandbox detonation of agent-submitted samples and hashes
+    SandboxDetonation,
+    /// On-demand YARA scans on Linux agents
+    YaraScanning,
+}
+
+impl Flag {
+    pub const ALL: [Flag; 4] = [Flag::MemoryAcquisition, Flag::PcapCapture, Flag::SandboxDetonation, Flag::YaraScanning];
+
+    /// flag_key in feature_flags
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Flag::MemoryAcquisition => "memory_acquisition",
+            Flag::PcapCapture => "pcap_capture",
+            Flag::SandboxDetonation => "sandbox_detonation",
+            Flag::YaraScanning => "yara_scanning",
+        }
+    }
+
+    pub fn parse(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.as_str() == key)
+    }
+
+    /// Value without any feature_flags row
+    pub fn default_enabled(&self) -> bool {
+        true
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Flag::MemoryAcquisition => "Operator-requested memory images from Linux agents",
+            Flag::PcapCapture => "Packet captures claimed by DPI probes",
+            Flag::SandboxDetonation => "Sandbox submissions from Linux agents",
+            Flag::YaraScanning => "On-demand YARA scans on Linux agents",
+        }
+    }
+}
+
+/// Where an effective value comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FlagSource {
+    Default,
+    Global,
+    Tenant,
+}
+
+/// One feature_flags row.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct FlagSetting {
+    pub flag: String,
+    /// None: the global value
+    pub tenant_id: Option<String>,
+    pub enabled: bool,
+    pub reason: String,
+    pub updated_by: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default)]
+struct Snapshot {
+    global: HashMap<Flag, bool>,
+    tenants: HashMap<(Flag, String), bool>,
+}
+
+/// In-memory snapshot of feature_flags consulted on every gated request.
+#[derive(Debug, Default)]
+pub struct FeatureFlags {
+    snapshot: RwLock<Snapshot>,
+}
+
+impl FeatureFlags {
+    /// Defaults only, until loaded
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Effective value of `flag` for an agent of `tenant` (None: no tenant), and its source.
+    pub fn resolve(&self, flag: Flag, tenant: Option<&str>) -> (bool, FlagSource) {
+        let snapshot = self.snapshot.read();
+        if let Some(enabled) = tenant.and_then(|t| snapshot.tenants.get(&(flag, t.to_string()))) {
+            return (*enabled, FlagSource::Tenant);
+        }
+        match snapshot.global.get(&flag) {
+            Some(enabled) => (*enabled, FlagSource::Global),
+            None => (flag.default_enabled(), FlagSource::Default),
+        }
+    }
+
+    pub fn is_enabled(&self, flag: Flag, tenant: Option<&str>) -> bool {
+        self.resolve(flag, tenant).0
+    }
+
+    /// Replace the snapshot. Rows of flags this build does not know are ignored (a newer
+    /// service may share the table).
+    pub fn replace(&self, rows: &[FlagSetting]) {
+        let mut snapshot = Snapshot::default();
+        for row in rows {
+            let Some(flag) = Flag::parse(&row.flag) else {
+                continue;
+            };
+            match &row.tenant_id {
+                Some(tenant) => snapshot.tenants.insert((flag, tenant.clone()), row.enabled),
+                None => snapshot.global.insert(flag, row.enabled),
+            };
+        }
+        *self.snapshot.write() = snapshot;
+    }
+
+    /// Reload from feature_flags; returns the rows loaded.
+    pub async fn load_from_db(&self, db: &Client) -> Result<usize, String> {
+        let rows = read_settings(db).await.map_err(|e| format!("Failed to read feature_flags: {}", e))?;
+        self.replace(&rows);
+        Ok(rows.len())
+    }
+
+    /// Poll feature_flags so changes made through other instances reach this one.
+    /// A failed refresh keeps the last loaded snapshot.
+    pub fn spawn_refresh(self: &Arc<Self>, db: Arc<Client>) -> Result<tokio::task::JoinHandle<()>, String> {
+        let interval = refresh_interval_from_env()?;
+        let flags = self.clone();
+        Ok(tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                if let Err(e) = flags.load_from_db(&db).await {
+                    warn!("Feature flag refresh failed (keeping previous flags): {}", e);
+                }
+            }
+        }))
+    }
+}
+
+/// Every feature_flags row, global rows first.
+pub async fn read_settings(db: &Client) -> Result<Vec<FlagSetting>, tokio_postgres::Error> {
+    let rows = db
+        .query(
+            r#"
+            SELECT flag_key, tenant_id, enabled, reason, updated_by, updated_at
+            FROM feature_flags
+            ORDER BY flag_key, tenant_id NULLS FIRST
+            "#,
+            &[],
+        )
+        .await?;
+    Ok(rows
+        .iter()
+        .map(|r| FlagSetting {
+            flag: r.get(0),
+            tenant_id: r.get(1),
+            enabled: r.get(2),
+            reason: r.get(3),
+            updated_by: r.get(4),
+            updated_at: r.get(5),
+        })
+        .collect())
+}
+
+/// tenant_id of an agent (None: unknown agent or no tenant).
+pub async fn agent_tenant(db: &Client, agent_id: uuid::Uuid) -> Result<Option<String>, tokio_postgres::Error> {
+    let row = db.query_opt("SELECT tenant_id FROM agents WHERE agent_id = $1", &[&agent_id]).await?;
+    Ok(row.and_then(|r| r.get(0)))
+}
+
+/// A tenant_id an override may name.
+pub fn check_tenant(tenant: &str) -> Result<(), String> {
+    if tenant.trim().is_empty() || tenant.trim() != tenant || tenant.chars().count() > MAX_TENANT_CHARS {
+        return Err(format!("tenant_id must be 1-{} characters without surrounding spaces", MAX_TENANT_CHARS));
+    }
+    if tenant.chars().any(char::is_control) {
+        return Err("tenant_id must not contain control characters".to_string());
+    }
+    Ok(())
+}
+
+fn refresh_interval_from_env() -> Result<Duration, String> {
+    match std::env::var("RANSOMEYE_INGEST_FEATURE_FLAG_REFRESH_SECS") {
+        Ok(v) => v
+            .parse::<u64>()
+            .ok()
+            .filter(|n| *n > 0)
+            .map(Duration::from_secs)
+            .ok_or_else(|| format!("Invalid RANSOMEYE_INGEST_FEATURE_FLAG_REFRESH_SECS '{}'", v)),
+        Err(_) => Ok(Duration::from_secs(DEFAULT_REFRESH_SECS)),
+    }
+}
@@ -0,0 +1,309 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/handoff.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Zero-downtime ingest restarts - listener acquisition (systemd socket activation or SO_REUSEPORT), in-flight request tracking, graceful draining and the control-file handoff between an old and a new instance
+
+/*
+ * Listener Handoff and Draining
+ *
+ * The listening socket comes from one of:
+ *
+ *   1. systemd socket activation (LISTEN_PID/LISTEN_FDS, first fd) - the socket unit keeps the
+ *      socket open across restarts, so connections queue in its backlog while the service restarts
+ *   2. a fresh bind, with SO_REUSEPORT when RANSOMEYE_INGEST_REUSEPORT is enabled so a second
+ *      instance can bind the same address while the first is still serving
+ *
+ * Draining stops accepting, lets in-flight requests finish, and gives up after
+ * RANSOMEYE_INGEST_DRAIN_TIMEOUT_SECS. It starts on SIGTERM/ctrl-c, or through the handoff file
+ * (RANSOMEYE_INGEST_HANDOFF_FILE):
+ *
+ *   1. the new instance binds (SO_REUSEPORT) and starts accepting next to the old one
+ *   2. it atomically writes the handoff file naming its pid and boot_id
+ *   3. the old instance sees another live pid in the file and drains; it heartbeats `draining`
+ *
+ * Handoff needs a socket both instances can hold: SO_REUSEPORT or an inherited systemd socket.
+ * Without either, startup fails (FAIL-CLOSED) instead of racing for the port.
+ */
+
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::net::{TcpListener, TcpSocket};
+use tokio::sync::Notify;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// First fd passed by systemd socket activation (SD_LISTEN_FDS_START).
+const SD_LISTEN_FDS_START: i32 = 3;
+const LISTEN_BACKLOG: u32 = 1024;
+const HANDOFF_POLL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone)]
+pub struct ListenerConfig {
+    pub reuse_port: bool,
+    pub handoff_file: Option<PathBuf>,
+    pub drain_timeout: Duration,
+}
+
+impl ListenerConfig {
+    pub fn from_env() -> Result<Self, String> {
+        let reuse_port = std::env::var("RANSOMEYE_INGEST_REUSEPORT")
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+        let handoff_file = match std::env::var("RANSOMEYE_INGEST_HANDOFF_FILE") {
+            Ok(v) if !v.is_empty() => Some(PathBuf::from(v)),
+            _ => None,
+        };
+        let drain_timeout = match std::env::var("RANSOMEYE_INGEST_DRAIN_TIMEOUT_SECS") {
+            Ok(v) => v
+                .parse::<u64>()
+                .ok()
+                .filter(|n| *n > 0)
+                .map(Duration::from_secs)
+                .ok_or_else(|| format!("Invalid RANSOMEYE_INGEST_DRAIN_TIMEOUT_SECS '{}' (expected integer > 0)", v))?,
+            Err(_) => Duration::from_secs(30),
+        };
+        Ok(Self { reuse_port, handoff_file, drain_timeout })
+    }
+}
+
+/// Where the listening socket came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListenerSource {
+    Systemd,
+    ReusePort,
+    Bound,
+}
+
+impl ListenerSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ListenerSource::Systemd => "systemd",
+            ListenerSource::ReusePort => "reuseport",
+            ListenerSource::Bound => "bound",
+        }
+    }
+
+    /// Another instance can accept on the same address while this one drains.
+    pub fn supports_handoff(&self) -> bool {
+        !matches!(self, ListenerSource::Bound)
+    }
+}
+
+/// Socket passed by systemd, if LISTEN_PID names this process. Only the first fd is used.
+fn systemd_listener() -> Result<Option<std::net::TcpListener>, String> {
+    let (Ok(pid), Ok(fds)) = (std::env::var("LISTEN_PID"), std::env::var("LISTEN_FDS")) else {
+        return Ok(None);
+    };
+    if pid.parse::<u32>().ok() != Some(std::process::id()) {
+        return Ok(None);
+    }
+    let count = fds
+        .parse::<i32>()
+        .map_err(|e| format!("Invalid LISTEN_FDS '{}': {}", fds, e))?;
+    if count < 1 {
+        return Ok(None);
+    }
+    if count > 1 {
+        warn!("systemd passed {} sockets; ingest uses only the first", count);
+    }
+    // SAFETY: systemd hands over ownership of fd 3 (LISTEN_PID matched this process) and nothing
+    // else in this process takes it.
+    let listener = unsafe { <std::net::TcpListener as std::os::fd::FromRawFd>::from_raw_fd(SD_LISTEN_FDS_START) };
+    listener
+        .set_nonblocking(true)
+        .map_err(|e| format!("systemd socket is not usable: {}", e))?;
+    Ok(Some(listener))
+}
+
+/// Listening socket for `addr` per `cfg` - FAIL-CLOSED if handoff is configured on a plain bind.
+pub fn acquire_listener(addr: &str, cfg: &ListenerConfig) -> Result<(TcpListener, ListenerSource), String> {
+    if let Some(std_listener) = systemd_listener()? {
+        let listener = TcpListener::from_std(std_listener).map_err(|e| format!("systemd socket: {}", e))?;
+        return Ok((listener, ListenerSource::Systemd));
+    }
+
+    if cfg.handoff_file.is_some() && !cfg.reuse_port {
+        return Err(
+            "FAIL-CLOSED: RANSOMEYE_INGEST_HANDOFF_FILE needs RANSOMEYE_INGEST_REUSEPORT=true or a systemd socket".to_string(),
+        );
+    }
+
+    let sock_addr: SocketAddr = addr
+        .parse()
+        .map_err(|e| format!("Invalid ingest listen address '{}': {}", addr, e))?;
+    let socket = if sock_addr.is_ipv4() { TcpSocket::new_v4() } else { TcpSocket::new_v6() }
+        .map_err(|e| format!("socket: {}", e))?;
+    socket.set_reuseaddr(true).map_err(|e| format!("SO_REUSEADDR: {}", e))?;
+    if cfg.reuse_port {
+        socket.set_reuseport(true).map_err(|e| format!("SO_REUSEPORT: {}", e))?;
+    }
+    socket.bind(sock_addr).map_err(|e| format!("bind {}: {}", addr, e))?;
+    let listener = socket.listen(LISTEN_BACKLOG).map_err(|e| format!("listen {}: {}", addr, e))?;
+    let source = if cfg.reuse_port { ListenerSource::ReusePort } else { ListenerSource::Bound };
+    Ok((listener, source))
+}
+
+/// Drain state shared by the server, the signal handler, the handoff watcher and the heartbeat.
+#[derive(Debug, Default)]
+pub struct DrainController {
+    draining: AtomicBool,
+    in_flight: AtomicUsize,
+    reason: Mutex<Option<String>>,
+    notify: Notify,
+}
+
+impl DrainController {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start draining; returns false if already draining.
+    pub fn begin(&self, reason: &str) -> bool {
+        if self.draining.swap(true, Ordering::SeqCst) {
+            return false;
+        }
+        *self.reason.lock() = Some(reason.to_string());
+        info!("Draining ingest ({}) | in_flight={}", reason, self.in_flight());
+        self.notify.notify_waiters();
+        true
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    pub fn reason(&self) -> Option<String> {
+        self.reason.lock().clone()
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once draining has started.
+    pub async fn wait(&self) {
+        loop {
+            let notified = self.notify.notified();
+            if self.is_draining() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Count a request for its whole lifetime.
+    pub fn track(self: &Arc<Self>) -> InFlightGuard {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        InFlightGuard(self.clone())
+    }
+}
+
+pub struct InFlightGuard(Arc<DrainController>);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Router layer counting in-flight requests (logged while draining).
+pub async fn track_in_flight(State(drain): State<Arc<DrainController>>, req: Request<Body>, next: Next) -> Response {
+    let _guard = drain.track();
+    next.run(req).await
+}
+
+/// Contents of RANSOMEYE_INGEST_HANDOFF_FILE: the instance that should be serving.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HandoffRecord {
+    pub pid: u32,
+    pub boot_id: Uuid,
+    pub listen_addr: String,
+    pub announced_at: DateTime<Utc>,
+}
+
+impl HandoffRecord {
+    pub fn new(boot_id: Uuid, listen_addr: &str) -> Self {
+        Self {
+            pid: std::process::id(),
+            boot_id,
+            listen_addr: listen_addr.to_string(),
+            announced_at: Utc::now(),
+        }
+    }
+}
+
+/// Atomically replace the handoff file (write + rename in the same directory).
+pub fn announce(path: &Path, record: &HandoffRecord) -> Result<(), String> {
+    let body = serde_json::to_vec_pretty(record).map_err(|e| format!("serialize handoff record: {}", e))?;
+    let tmp = path.with_extension(format!("tmp.{}", record.pid));
+    std::fs::write(&tmp, body).map_err(|e| format!("write {}: {}", tmp.display(), e))?;
+    std::fs::rename(&tmp, path).map_err(|e| format!("rename {} -> {}: {}", tmp.display(), path.display(), e))
+}
+
+/// Current handoff record; None if the file does not exist.
+pub fn read_record(path: &Path) -> Result<Option<HandoffRecord>, String> {
+    match std::fs::read(path) {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|e| format!("invalid handoff file {}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("read {}: {}", path.display(), e)),
+    }
+}
+
+/// Remove the handoff file on a normal stop, unless a successor already took it over.
+pub fn release(path: &Path, boot_id: Uuid) {
+    match read_record(path) {
+        Ok(Some(record)) if record.boot_id == boot_id => {
+            if let Err(e) = std::fs::remove_file(path) {
+                warn!("Failed to remove handoff file {}: {}", path.display(), e);
+            }
+        }
+        Ok(_) => {}
+        Err(e) => warn!("Handoff file not released: {}", e),
+    }
+}
+
+/// A successor (another live process) has announced itself.
+pub fn successor(record: &HandoffRecord, own_boot_id: Uuid) -> bool {
+    record.boot_id != own_boot_id
+        && record.pid != std::process::id()
+        && Path::new(&format!("/proc/{}", record.pid)).exists()
+}
+
+/// Poll the handoff file and start draining once a successor announces itself.
+pub fn spawn_watcher(path: PathBuf, own_boot_id: Uuid, drain: Arc<DrainController>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(HANDOFF_POLL);
+        loop {
+            tick.tick().await;
+            if drain.is_draining() {
+                return;
+            }
+            match read_record(&path) {
+                Ok(Some(record)) if successor(&record, own_boot_id) => {
+                    info!(
+                        "Handoff: successor pid={} boot_id={} is accepting on {}",
+                        record.pid, record.boot_id, record.listen_addr
+                    );
+                    drain.begin("handoff");
+                    return;
+                }
+                Ok(_) => {}
+                Err(e) => error!("Handoff watcher: {}", e),
+            }
+        }
+    })
+}
@@ -0,0 +1,90 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/host_inventory.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Host inventory reports - validation of agent-reported host facts (OS, kernel, CPU/RAM, security tooling, disk encryption) into the host_inventory row of the reporting agent
+
+/*
+ * Host Inventory
+ *
+ * Linux agents send a host_inventory event at startup and once a day. The event is stored like
+ * any other telemetry; in the same unit of work its facts replace the agent's host_inventory row,
+ * unless the row already holds a report collected later (a replayed or spooled older report
+ * never overwrites newer facts).
+ *
+ * A report that fails validation is refused with 400 before anything is written: the inventory is
+ * used for vulnerability and coverage analysis, so a partial or oversized report is not stored.
+ */
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::protocol::signed_event::LinuxHostInventory;
+use crate::storage::{DiskEncryption, HostInventoryRecord, SecurityTool};
+
+const MAX_FIELD_LEN: usize = 256;
+const MAX_SECURITY_TOOLS: usize = 64;
+const MAX_ENCRYPTED_VOLUMES: usize = 64;
+
+fn field(name: &str, value: Option<&str>) -> Result<Option<String>, String> {
+    match value.map(str::trim).filter(|v| !v.is_empty()) {
+        Some(v) if v.len() > MAX_FIELD_LEN => Err(format!("{} longer than {} bytes", name, MAX_FIELD_LEN)),
+        Some(v) => Ok(Some(v.to_string())),
+        None => Ok(None),
+    }
+}
+
+fn required(name: &str, value: Option<&str>) -> Result<String, String> {
+    field(name, value)?.ok_or_else(|| format!("{} missing", name))
+}
+
+/// Validated host_inventory row for `agent_id` from one report (event_id and timestamp of its envelope).
+pub fn to_record(
+    agent_id: Uuid,
+    source_event_id: &str,
+    collected_at: DateTime<Utc>,
+    report: &LinuxHostInventory,
+) -> Result<HostInventoryRecord, String> {
+    if report.security_tools.len() > MAX_SECURITY_TOOLS {
+        return Err(format!("more than {} security_tools", MAX_SECURITY_TOOLS));
+    }
+    if report.encrypted_volumes.len() > MAX_ENCRYPTED_VOLUMES {
+        return Err(format!("more than {} encrypted_volumes", MAX_ENCRYPTED_VOLUMES));
+    }
+    let security_tools = report
+        .security_tools
+        .iter()
+        .map(|t| {
+            Ok(SecurityTool {
+                name: required("security_tools.name", Some(&t.name))?,
+                kind: required("security_tools.kind", Some(&t.kind))?,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let encrypted_volumes = report
+        .encrypted_volumes
+        .iter()
+        .map(|v| required("encrypted_volumes", Some(v)))
+        .collect::<Result<Vec<_>, String>>()?;
+    let disk_encryption = match report.disk_encryption.as_deref() {
+        Some(v) => DiskEncryption::parse(v).ok_or_else(|| format!("disk_encryption '{}' not supported", v))?,
+        None => DiskEncryption::Unknown,
+    };
+
+    Ok(HostInventoryRecord {
+        agent_id,
+        hostname: field("hostname", report.hostname.as_deref())?,
+        os_name: field("os_name", report.os_name.as_deref())?,
+        os_version: field("os_version", report.os_version.as_deref())?,
+        kernel_release: required("kernel_release", report.kernel_release.as_deref())?,
+        kernel_version: field("kernel_version", report.kernel_version.as_deref())?,
+        arch: required("arch", report.arch.as_deref())?,
+        cpu_model: field("cpu_model", report.cpu_model.as_deref())?,
+        cpu_cores: report.cpu_cores.unwrap_or(0),
+        memory_total_bytes: report.memory_total_bytes.unwrap_or(0),
+        security_tools,
+        disk_encryption,
+        encrypted_volumes,
+        source_event_id: source_event_id.to_string(),
+        collected_at,
+        received_at: Utc::now(),
+    })
+}
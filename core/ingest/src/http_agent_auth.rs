@@ -0,0 +1,692 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/http_agent_auth.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Per-agent bearer token authentication for the HTTP ingestion server - enrollment issuance, rotation, revocation (DB-backed), middleware mapping tokens to agent identity, and operator-approved signing key rotation
+
+use std::sync::Arc;
+use axum::{
+    extract::{Request, State},
+    http::{HeaderMap, StatusCode},
+    middleware::Next,
+    response::{Json, Response},
+    Extension,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crypto::digest::Sha256;
+use tokio_postgres::Client;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::agent_token::{self, AgentTokenError, AgentTokenSigner};
+use crate::storage::postgres::get_or_create_agent;
+use crate::storage::{self, AuditRecord, TelemetryStore};
+
+use crate::http_server::AppState;
+use crate::residency;
+use crate::signature_verification::{self, PublicKey};
+use crate::suppression;
+use crate::webhooks;
+
+/// Agent identity resolved from a verified bearer token (inserted as a request extension).
+#[derive(Debug, Clone)]
+pub struct AuthenticatedAgent {
+    pub agent_id: Uuid,
+    pub token_id: Uuid,
+    pub agent_type: String,
+    /// host_hostname registered at enrollment; must match envelope.component_id.
+    pub component_identity: String,
+    /// agents.residency_region (None: enrolled before residency tagging)
+    pub region: Option<String>,
+    /// agents.tenant_id (None: single-tenant deployment)
+    pub tenant_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IngestAuthMode {
+    /// Bearer token REQUIRED on ingest endpoints (default, fail-closed).
+    Token,
+    /// No token check (legacy agents only; logs a warning on every request).
+    Disabled,
+}
+
+pub struct AgentTokenAuthority {
+    pub mode: IngestAuthMode,
+    signer: Option<AgentTokenSigner>,
+    enrollment_key: Option<Vec<u8>>,
+    pub token_ttl: Duration,
+    pub rotation_grace: Duration,
+}
+
+impl AgentTokenAuthority {
+    /// Load from environment (FAIL-CLOSED: token mode without a usable key aborts startup).
+    pub fn from_env() -> Result<Self, String> {
+        let mode = match std::env::var("RANSOMEYE_INGEST_AUTH_MODE")
+            .unwrap_or_else(|_| "token".to_string())
+            .as_str()
+        {
+            "token" => IngestAuthMode::Token,
+            "disabled" => IngestAuthMode::Disabled,
+            other => return Err(format!("Invalid RANSOMEYE_INGEST_AUTH_MODE '{}' (expected token|disabled)", other)),
+        };
+
+        let token_ttl_secs = env_i64("RANSOMEYE_INGEST_TOKEN_TTL_SECS", 30 * 24 * 3600)?;
+        let rotation_grace_secs = env_i64("RANSOMEYE_INGEST_TOKEN_ROTATION_GRACE_SECS", 3600)?;
+        if token_ttl_secs <= 0 || rotation_grace_secs < 0 {
+            return Err("Token TTL must be > 0 and rotation grace must be >= 0".to_string());
+        }
+
+        let signer = match std::env::var("RANSOMEYE_INGEST_TOKEN_KEY_PATH") {
+            Ok(path) => Some(AgentTokenSigner::from_key_file(&path).map_err(|e| format!("FAIL-CLOSED: {}", e))?),
+            Err(_) if mode == IngestAuthMode::Token => {
+                return Err("FAIL-CLOSED: RANSOMEYE_INGEST_TOKEN_KEY_PATH must be set when RANSOMEYE_INGEST_AUTH_MODE=token".to_string());
+            }
+            Err(_) => None,
+        };
+
+        let enrollment_key = match std::env::var("RANSOMEYE_ENROLLMENT_KEY_PATH") {
+            Ok(path) => {
+                let raw = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("FAIL-CLOSED: cannot read enrollment key {}: {}", path, e))?;
+                let key = raw.trim().as_bytes().to_vec();
+                if key.len() < 16 {
+                    return Err("FAIL-CLOSED: enrollment key must be at least 16 bytes".to_string());
+                }
+                Some(key)
+            }
+            Err(_) => None,
+        };
+
+        if mode == IngestAuthMode::Disabled {
+            warn!("Ingest token authentication DISABLED (RANSOMEYE_INGEST_AUTH_MODE=disabled)");
+        }
+
+        Ok(Self {
+            mode,
+            signer,
+            enrollment_key,
+            token_ttl: Duration::seconds(token_ttl_secs),
+            rotation_grace: Duration::seconds(rotation_grace_secs),
+        })
+    }
+
+    fn signer(&self) -> Result<&AgentTokenSigner, StatusCode> {
+        self.signer.as_ref().ok_or_else(|| {
+            error!("Token operation requested but no token key is configured");
+            StatusCode::SERVICE_UNAVAILABLE
+        })
+    }
+
+    /// Constant-time check of the X-Enrollment-Key header (enrollment and revocation are operator actions).
+    fn check_enrollment_key(&self, headers: &HeaderMap) -> Result<(), StatusCode> {
+        let Some(expected) = &self.enrollment_key else {
+            error!("Enrollment/revocation requested but RANSOMEYE_ENROLLMENT_KEY_PATH is not configured");
+            return Err(StatusCode::SERVICE_UNAVAILABLE);
+        };
+        let provided = headers
+            .get("x-enrollment-key")
+            .and_then(|v| v.to_str().ok())
+            .ok_or(StatusCode::UNAUTHORIZED)?;
+        // Compare digests so lengths never leak through timing.
+        let a = Sha256::digest(expected);
+        let b = Sha256::digest(provided.trim().as_bytes());
+        if crypto::constant_time_eq(&a, &b) {
+            Ok(())
+        } else {
+            warn!("Rejected request with invalid enrollment key");
+            Err(StatusCode::UNAUTHORIZED)
+        }
+    }
+}
+
+/// Middleware for ingest routes: verify bearer token and map it to agent identity before the
+/// handler performs any signature verification.
+pub async fn require_agent_token(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    if state.tokens.mode == IngestAuthMode::Disabled {
+        return Ok(next.run(request).await);
+    }
+
+    let header = request
+        .headers()
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok());
+    let agent = authenticate_bearer(&state, header).await.map_err(|e| {
+        warn!("AUTH REJECT: {}", e);
+        StatusCode::UNAUTHORIZED
+    })?;
+
+    request.extensions_mut().insert(agent);
+    Ok(next.run(request).await)
+}
+
+async fn authenticate_bearer(state: &AppState, header: Option<&str>) -> Result<AuthenticatedAgent, AgentTokenError> {
+    let token = agent_token::parse_bearer(header)?;
+    let signer = state
+        .tokens
+        .signer
+        .as_ref()
+        .ok_or_else(|| AgentTokenError::KeyInvalid("no token key configured".to_string()))?;
+    let claims = signer.verify(token, Utc::now())?;
+    let db = state
+        .db
+        .as_ref()
+        .ok_or_else(|| AgentTokenError::KeyInvalid("agent token store requires postgres backend".to_string()))?;
+
+    // DB row is authoritative for revocation, rotation expiry, and agent binding.
+    let row = db
+        .query_opt(
+            r#"
+            SELECT t.agent_id, t.token_sha256, t.expires_at, t.revoked_at, t.revoked_reason,
+                   a.agent_type::text, a.host_hostname, a.is_active, a.residency_region, a.tenant_id
+            FROM agent_api_tokens t
+            JOIN agents a ON a.agent_id = t.agent_id
+            WHERE t.token_id = $1
+            "#,
+            &[&claims.token_id],
+        )
+        .await
+        .map_err(|e| {
+            error!("Token lookup failed: {}", e);
+            AgentTokenError::Unknown(claims.token_id)
+        })?
+        .ok_or(AgentTokenError::Unknown(claims.token_id))?;
+
+    let agent_id: Uuid = row.get(0);
+    let stored_sha256: Vec<u8> = row.get(1);
+    let expires_at: DateTime<Utc> = row.get(2);
+    let revoked_at: Option<DateTime<Utc>> = row.get(3);
+    let revoked_reason: Option<String> = row.get(4);
+    let agent_type: String = row.get(5);
+    let host_hostname: Option<String> = row.get(6);
+    let is_active: bool = row.get(7);
+    let region: Option<String> = row.get(8);
+    let tenant_id: Option<String> = row.get(9);
+
+    if !crypto::constant_time_eq(&stored_sha256, &agent_token::token_sha256(token)) {
+        return Err(AgentTokenError::Unknown(claims.token_id));
+    }
+    if agent_id != claims.agent_id {
+        return Err(AgentTokenError::AgentMismatch(claims.agent_id.to_string()));
+    }
+    if let Some(at) = revoked_at {
+        return Err(AgentTokenError::Revoked(format!(
+            "{} at {}",
+            revoked_reason.unwrap_or_else(|| "unspecified".to_string()),
+            at.to_rfc3339()
+        )));
+    }
+    // Rotation shortens expires_at in the DB; honour the stricter of token and row.
+    if expires_at <= Utc::now() {
+        return Err(AgentTokenError::Expired(expires_at));
+    }
+    if !is_active {
+        return Err(AgentTokenError::Revoked("agent inactive".to_string()));
+    }
+    let component_identity = host_hostname.ok_or_else(|| AgentTokenError::AgentMismatch(agent_id.to_string()))?;
+
+    let _ = db
+        .execute(
+            "UPDATE agent_api_tokens SET last_used_at = NOW() WHERE token_id = $1",
+            &[&claims.token_id],
+        )
+        .await;
+
+    Ok(AuthenticatedAgent {
+        agent_id,
+        token_id: claims.token_id,
+        agent_type,
+        component_identity,
+        region,
+        tenant_id,
+    })
+}
+
+/// Handler-side binding check: the token's agent must be the envelope's producer.
+pub fn check_envelope_binding(
+    auth: Option<&AuthenticatedAgent>,
+    envelope_component_id: &str,
+    expected_agent_type: &str,
+) -> Result<(), StatusCode> {
+    let Some(auth) = auth else {
+        return Ok(());
+    };
+    if auth.component_identity != envelope_component_id || auth.agent_type != expected_agent_type {
+        error!(
+            "AUTH REJECT: token agent {} ({}/{}) does not match envelope component_id={} type={}",
+            auth.agent_id, auth.agent_type, auth.component_identity, envelope_component_id, expected_agent_type
+        );
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(())
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct EnrollRequest {
+    pub component_identity: String,
+    pub agent_type: String,
+    /// Signing key to pin at enrollment (otherwise pinned on first use)
+    #[serde(default)]
+    pub signer_id: Option<String>,
+    /// Hex Ed25519 public key event signatures are verified against (agents.signing_public_key)
+    #[serde(default)]
+    pub public_key: Option<String>,
+    /// Residency region the agent's data belongs to (default: this ingest instance's region)
+    #[serde(default)]
+    pub region: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TokenResponse {
+    pub agent_id: String,
+    pub token_id: String,
+    pub token: String,
+    pub expires_at: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RevokeRequest {
+    pub token_id: Option<Uuid>,
+    pub agent_id: Option<Uuid>,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RevokeResponse {
+    pub revoked: u64,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct KeyRotateRequest {
+    pub agent_id: Uuid,
+    pub new_signer_id: String,
+    pub reason: String,
+    /// Hex Ed25519 public key of the new signer (replaces agents.signing_public_key)
+    #[serde(default)]
+    pub new_public_key: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct KeyRotateResponse {
+    pub agent_id: String,
+    pub signer_id: String,
+    pub previous_signer_id: Option<String>,
+    pub previous_valid_until: Option<String>,
+}
+
+/// POST /agents/enroll (X-Enrollment-Key): register agent identity and issue its first token.
+#[utoipa::path(
+    post,
+    path = "/agents/enroll",
+    tag = "agents",
+    request_body = EnrollRequest,
+    responses(
+        (status = 200, description = "Agent registered; first token issued", body = TokenResponse),
+        (status = 400, description = "Invalid identity, agent type, region or public key"),
+        (status = 401, description = "Invalid enrollment key"),
+        (status = 409, description = "Agent already pinned to a different signing key, registered with a different public key or tagged with a different region"),
+        (status = 503, description = "Token authentication not configured"),
+    ),
+    security(("enrollment_key" = []))
+)]
+pub async fn handle_enroll(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<EnrollRequest>,
+) -> Result<Json<TokenResponse>, StatusCode> {
+    state.tokens.check_enrollment_key(&headers)?;
+    if req.component_identity.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if !matches!(req.agent_type.as_str(), "linux_agent" | "windows_agent" | "dpi_probe") {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let region = state.residency.enrollment_region(req.region.as_deref()).map_err(|e| {
+        warn!("Enrollment refused: {}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+    let public_key = parse_request_key(req.public_key.as_deref())?;
+
+    let agent_id = get_or_create_agent(control_db(&state)?, &req.component_identity, &req.agent_type)
+        .await
+        .map_err(|e| {
+            error!("Enrollment failed to resolve agent: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    // Unauthenticated ingest must re-read this identity's agents row after (re-)enrollment
+    state.agent_cache.invalidate_identity(&req.component_identity);
+    state.dashboard.invalidate_fleet();
+
+    // Re-enrollment must not become a way around operator-approved key rotation
+    if let Some(signer_id) = req.signer_id.as_deref().filter(|s| !s.is_empty()) {
+        match state.key_pins.pin_at_enrollment(agent_id, signer_id).await {
+            Ok(None) => {}
+            Ok(Some(existing)) => {
+                warn!(
+                    "Enrollment refused: agent {} is pinned to signer_id={} (presented {}); use /agents/key/rotate",
+                    agent_id, existing.signer_id, signer_id
+                );
+                return Err(StatusCode::CONFLICT);
+            }
+            Err(e) => {
+                error!("FAIL-CLOSED: Failed to pin enrollment key: {}", e);
+                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+        }
+    }
+
+    // Like the pin, a registered public key changes only through /agents/key/rotate
+    if let Some(key) = &public_key {
+        match signature_verification::register_agent_key(control_db(&state)?, agent_id, key).await {
+            Ok(None) => state.signatures.invalidate(agent_id),
+            Ok(Some(_)) => {
+                warn!("Enrollment refused: agent {} is registered with a different public key; use /agents/key/rotate", agent_id);
+                return Err(StatusCode::CONFLICT);
+            }
+            Err(e) => {
+                error!("FAIL-CLOSED: Failed to register agent public key: {}", e);
+                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+        }
+    }
+
+    // Region tags are permanent: moving an agent's data to another region is not an enrollment
+    if let Some(region) = region.as_deref() {
+        match residency::tag_agent_region(control_db(&state)?, agent_id, region).await {
+            Ok(None) => {}
+            Ok(Some(existing)) => {
+                warn!(
+                    "Enrollment refused: agent {} is tagged with region {} (requested {})",
+                    agent_id, existing, region
+                );
+                return Err(StatusCode::CONFLICT);
+            }
+            Err(e) => {
+                error!("FAIL-CLOSED: Failed to tag agent residency region: {}", e);
+                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+        }
+    }
+
+    let response = issue_and_store(&state, agent_id, None, "AGENT_TOKEN_ISSUED").await?;
+    info!("Agent enrolled | agent_id={} | token_id={}", agent_id, response.token_id);
+
+    // The token is already issued; a lost notification must not fail the enrollment
+    let event = webhooks::WebhookEvent::new(
+        webhooks::AGENT_ENROLLED,
+        serde_json::json!({
+            "agent_id": agent_id.to_string(),
+            "component_identity": req.component_identity,
+            "agent_type": req.agent_type,
+            "region": region,
+            "token_id": response.token_id,
+            "expires_at": response.expires_at,
+        }),
+    );
+    if let Err(e) = webhooks::enqueue(control_db(&state)?, &event).await {
+        error!("Failed to enqueue {} webhook for agent {}: {}", webhooks::AGENT_ENROLLED, agent_id, e);
+    }
+    Ok(Json(response))
+}
+
+/// POST /agents/token/rotate (Bearer): issue a replacement token; the presented token stays valid
+/// only for the configured grace period.
+#[utoipa::path(
+    post,
+    path = "/agents/token/rotate",
+    tag = "agents",
+    responses(
+        (status = 200, description = "Replacement token issued", body = TokenResponse),
+        (status = 401, description = "Missing or invalid agent token"),
+    ),
+    security(("agent_token" = []))
+)]
+pub async fn handle_rotate(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthenticatedAgent>,
+) -> Result<Json<TokenResponse>, StatusCode> {
+    let response = issue_and_store(&state, auth.agent_id, Some(auth.token_id), "AGENT_TOKEN_ROTATED").await?;
+
+    let grace_until = Utc::now() + state.tokens.rotation_grace;
+    control_db(&state)?
+        .execute(
+            r#"
+            UPDATE agent_api_tokens
+            SET expires_at = LEAST(expires_at, $2)
+            WHERE token_id = $1
+            "#,
+            &[&auth.token_id, &grace_until],
+        )
+        .await
+        .map_err(|e| {
+            error!("FAIL-CLOSED: Failed to shorten rotated token expiry: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    info!(
+        "Agent token rotated | agent_id={} | old_token_id={} | new_token_id={}",
+        auth.agent_id, auth.token_id, response.token_id
+    );
+    Ok(Json(response))
+}
+
+/// POST /agents/token/revoke (X-Enrollment-Key): revoke one token or all tokens of an agent.
+#[utoipa::path(
+    post,
+    path = "/agents/token/revoke",
+    tag = "agents",
+    request_body = RevokeRequest,
+    responses(
+        (status = 200, description = "Tokens revoked", body = RevokeResponse),
+        (status = 400, description = "Neither token_id nor agent_id, or empty reason"),
+        (status = 401, description = "Invalid enrollment key"),
+    ),
+    security(("enrollment_key" = []))
+)]
+pub async fn handle_revoke(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<RevokeRequest>,
+) -> Result<Json<RevokeResponse>, StatusCode> {
+    state.tokens.check_enrollment_key(&headers)?;
+    if req.reason.trim().is_empty() || (req.token_id.is_none() && req.agent_id.is_none()) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let revoked = control_db(&state)?
+        .execute(
+            r#"
+            UPDATE agent_api_tokens
+            SET revoked_at = NOW(), revoked_reason = $3
+            WHERE revoked_at IS NULL
+              AND ($1::uuid IS NULL OR token_id = $1)
+              AND ($2::uuid IS NULL OR agent_id = $2)
+            "#,
+            &[&req.token_id, &req.agent_id, &req.reason],
+        )
+        .await
+        .map_err(|e| {
+            error!("FAIL-CLOSED: Token revocation failed: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let payload = serde_json::json!({
+        "token_id": req.token_id.map(|x| x.to_string()),
+        "agent_id": req.agent_id.map(|x| x.to_string()),
+        "reason": req.reason,
+        "revoked": revoked
+    });
+    audit(state.store.as_ref(), req.agent_id, "AGENT_TOKEN_REVOKED", req.token_id, &payload).await?;
+
+    info!("Agent tokens revoked | count={} | reason={}", revoked, req.reason);
+    Ok(Json(RevokeResponse { revoked }))
+}
+
+/// POST /agents/key/rotate (X-Enrollment-Key): operator-approved signing key rotation. The new key
+/// is pinned immediately; the replaced key stays accepted for the rotation grace window.
+#[utoipa::path(
+    post,
+    path = "/agents/key/rotate",
+    tag = "agents",
+    request_body = KeyRotateRequest,
+    responses(
+        (status = 200, description = "New signing key pinned", body = KeyRotateResponse),
+        (status = 400, description = "Empty new_signer_id or reason, or invalid new_public_key"),
+        (status = 401, description = "Invalid enrollment key"),
+        (status = 500, description = "Pin update failed (fail-closed)"),
+    ),
+    security(("enrollment_key" = []))
+)]
+pub async fn handle_key_rotate(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<KeyRotateRequest>,
+) -> Result<Json<KeyRotateResponse>, StatusCode> {
+    state.tokens.check_enrollment_key(&headers)?;
+    if req.new_signer_id.trim().is_empty() || req.reason.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let new_public_key = parse_request_key(req.new_public_key.as_deref())?;
+    control_db(&state)?;
+
+    let pin = state
+        .key_pins
+        .approve_rotation(req.agent_id, &req.new_signer_id, &req.reason)
+        .await
+        .map_err(|e| {
+            error!("FAIL-CLOSED: Agent key rotation failed: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    if let Some(key) = &new_public_key {
+        signature_verification::replace_agent_key(control_db(&state)?, req.agent_id, key).await.map_err(|e| {
+            error!("FAIL-CLOSED: Agent public key rotation failed: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+        state.signatures.invalidate(req.agent_id);
+    }
+
+    let payload = serde_json::json!({
+        "agent_id": req.agent_id.to_string(),
+        "signer_id": pin.signer_id,
+        "previous_signer_id": pin.previous_signer_id,
+        "previous_valid_until": pin.previous_valid_until.map(|t| t.to_rfc3339()),
+        "public_key_replaced": new_public_key.is_some(),
+        "reason": req.reason
+    });
+    audit(state.store.as_ref(), Some(req.agent_id), "AGENT_KEY_ROTATION_APPROVED", Some(req.agent_id), &payload).await?;
+
+    info!(
+        "Agent signing key rotated | agent_id={} | signer_id={} | previous={:?}",
+        req.agent_id, pin.signer_id, pin.previous_signer_id
+    );
+    Ok(Json(KeyRotateResponse {
+        agent_id: req.agent_id.to_string(),
+        signer_id: pin.signer_id,
+        previous_signer_id: pin.previous_signer_id,
+        previous_valid_until: pin.previous_valid_until.map(|t| t.to_rfc3339()),
+    }))
+}
+
+/// Optional hex public key of an enrollment or rotation request (400 if malformed).
+fn parse_request_key(value: Option<&str>) -> Result<Option<PublicKey>, StatusCode> {
+    match value.map(str::trim).filter(|v| !v.is_empty()) {
+        None => Ok(None),
+        Some(hex_key) => suppression::parse_public_key(hex_key).map(Some).map_err(|e| {
+            warn!("Invalid agent public key: {}", e);
+            StatusCode::BAD_REQUEST
+        }),
+    }
+}
+
+async fn issue_and_store(
+    state: &AppState,
+    agent_id: Uuid,
+    rotated_from: Option<Uuid>,
+    audit_action: &str,
+) -> Result<TokenResponse, StatusCode> {
+    let signer = state.tokens.signer()?;
+    let issued = signer.issue(agent_id, Utc::now() + state.tokens.token_ttl);
+
+    control_db(state)?
+        .execute(
+            r#"
+            INSERT INTO agent_api_tokens (token_id, agent_id, token_sha256, issued_at, expires_at, rotated_from_token_id)
+            VALUES ($1, $2, $3, NOW(), $4, $5)
+            "#,
+            &[
+                &issued.claims.token_id,
+                &agent_id,
+                &issued.token_sha256,
+                &issued.claims.expires_at,
+                &rotated_from,
+            ],
+        )
+        .await
+        .map_err(|e| {
+            error!("FAIL-CLOSED: Failed to store agent token: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    // Audit never contains the token itself.
+    let payload = serde_json::json!({
+        "agent_id": agent_id.to_string(),
+        "token_id": issued.claims.token_id.to_string(),
+        "rotated_from_token_id": rotated_from.map(|x| x.to_string()),
+        "expires_at": issued.claims.expires_at.to_rfc3339()
+    });
+    audit(state.store.as_ref(), Some(agent_id), audit_action, Some(issued.claims.token_id), &payload).await?;
+
+    Ok(TokenResponse {
+        agent_id: agent_id.to_string(),
+        token_id: issued.claims.token_id.to_string(),
+        token: issued.token,
+        expires_at: issued.claims.expires_at.to_rfc3339(),
+    })
+}
+
+/// Agent token tables live only in Postgres; the SQLite lab backend has no control plane.
+fn control_db(state: &AppState) -> Result<&Arc<Client>, StatusCode> {
+    state.db.as_ref().ok_or_else(|| {
+        error!("Agent token operation requested but storage backend has no control plane (postgres required)");
+        StatusCode::SERVICE_UNAVAILABLE
+    })
+}
+
+pub(crate) async fn audit(
+    store: &dyn TelemetryStore,
+    agent_id: Option<Uuid>,
+    action: &str,
+    object_id: Option<Uuid>,
+    payload: &serde_json::Value,
+) -> Result<Uuid, StatusCode> {
+    let component_id = store.ingestion_component().await.map_err(|e| {
+        error!("FAIL-CLOSED: Failed to get/create ingestion component: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let payload_str = serde_json::to_string(payload).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let record = AuditRecord {
+        actor_component_id: Some(component_id),
+        actor_agent_id: agent_id,
+        action: action.to_string(),
+        object_type: "other".to_string(),
+        object_id,
+        event_time: Some(Utc::now()),
+        payload_json: payload.clone(),
+        payload_sha256: Sha256::digest(payload_str.as_bytes()).to_vec(),
+    };
+    // Standalone audit rows get their own unit of work so the chain lock is held until commit.
+    storage::append_audit_standalone(store, &record).await.map_err(|e| {
+        error!("FAIL-CLOSED: Failed to insert {} audit log: {}", action, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+fn env_i64(key: &str, default_value: i64) -> Result<i64, String> {
+    match std::env::var(key) {
+        Ok(v) => v.parse::<i64>().map_err(|e| format!("Invalid {}='{}': {}", key, v, e)),
+        Err(_) => Ok(default_value),
+    }
+}
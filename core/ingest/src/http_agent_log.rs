@@ -0,0 +1,272 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/http_agent_log.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Sealed agent log segment upload - Linux agents ship hash-chained, signed log segments (agent bearer token); verified segments are recorded with their chain continuity and spooled for the evidence store
+
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::Json,
+    Extension,
+};
+use chrono::Utc;
+use tokio_postgres::Client;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::agent_log::{self, AgentLogConfig, Continuity, LogSegment, LogSegmentReceipt, Placement, SpoolEntry, StoredSegment};
+use crate::disk_quota;
+use crate::http_agent_auth::{self, AuthenticatedAgent};
+use crate::http_server::AppState;
+
+fn control_db(state: &AppState) -> Result<&Arc<Client>, StatusCode> {
+    state.db.as_ref().ok_or_else(|| {
+        error!("Agent log segment received but storage backend has no control plane (postgres required)");
+        StatusCode::SERVICE_UNAVAILABLE
+    })
+}
+
+fn log_config(state: &AppState) -> Result<&AgentLogConfig, StatusCode> {
+    state.agent_logs.as_deref().ok_or_else(|| {
+        warn!("Agent log segment received but sealed agent logs are off (RANSOMEYE_INGEST_AGENT_LOGS)");
+        StatusCode::SERVICE_UNAVAILABLE
+    })
+}
+
+fn db_err(what: &str) -> impl FnOnce(tokio_postgres::Error) -> StatusCode + '_ {
+    move |e| {
+        error!("FAIL-CLOSED: {}: {}", what, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+fn linux_agent(auth: Option<Extension<AuthenticatedAgent>>) -> Result<AuthenticatedAgent, StatusCode> {
+    let Some(Extension(auth)) = auth else {
+        warn!("AUTH REJECT: agent log segments require an agent token");
+        return Err(StatusCode::UNAUTHORIZED);
+    };
+    if auth.agent_type != "linux_agent" {
+        warn!("AUTH REJECT: agent {} ({}) is not a Linux agent", auth.agent_id, auth.agent_type);
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(auth)
+}
+
+/// POST /agents/log-segments (Bearer, Linux agent): one sealed segment of the agent's log hash
+/// chain. Lines and seal are verified, continuity with the agent's stored segments of the chain is
+/// recorded (a gap is accepted and audited) and the segment is spooled for the evidence store.
+#[utoipa::path(
+    post,
+    path = "/agents/log-segments",
+    tag = "agents",
+    request_body = LogSegment,
+    responses(
+        (status = 200, description = "Segment stored, or identical to the stored one", body = LogSegmentReceipt),
+        (status = 400, description = "Malformed segment, lines that do not hash to head_hash or a seal that does not verify"),
+        (status = 401, description = "Missing or invalid agent token"),
+        (status = 403, description = "Agent is not a Linux agent"),
+        (status = 409, description = "Fork, signer key change or another segment under a stored index"),
+        (status = 503, description = "Sealed agent logs off or postgres control plane not configured"),
+        (status = 507, description = "Agent log spool over its disk quota; retry after Retry-After seconds"),
+    ),
+    security(("agent_token" = []))
+)]
+pub async fn handle_upload_segment(
+    State(state): State<AppState>,
+    auth: Option<Extension<AuthenticatedAgent>>,
+    Json(segment): Json<LogSegment>,
+) -> Result<Json<LogSegmentReceipt>, StatusCode> {
+    let auth = linux_agent(auth)?;
+    let config = log_config(&state)?;
+    let db = control_db(&state)?;
+
+    let index = i64::try_from(segment.segment_index).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let first_line = i64::try_from(segment.first_line).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let dropped_before = i64::try_from(segment.segments_dropped_before).map_err(|_| StatusCode::BAD_REQUEST)?;
+    if let Err(e) = segment.verify() {
+        warn!(
+            "Rejected log segment {} of chain {} from agent {}: {}",
+            segment.segment_index, segment.chain_id, auth.component_identity, e
+        );
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let sealed_at = segment.sealed_at().map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let rows = db
+        .query(
+            r#"
+            SELECT segment_id, segment_index, prev_hash, head_hash, signer_key, continuity
+            FROM agent_log_segments
+            WHERE agent_id = $1 AND chain_id = $2 AND segment_index BETWEEN $3 - 1 AND $3 + 1
+            "#,
+            &[&auth.agent_id, &segment.chain_id, &index],
+        )
+        .await
+        .map_err(db_err("Failed to look up agent log segments"))?;
+    let neighbours: Vec<StoredSegment> = rows
+        .iter()
+        .map(|r| StoredSegment {
+            segment_index: r.get::<_, i64>(1) as u64,
+            prev_hash: hex::encode(r.get::<_, Vec<u8>>(2)),
+            head_hash: hex::encode(r.get::<_, Vec<u8>>(3)),
+            signer_key: hex::encode(r.get::<_, Vec<u8>>(4)),
+        })
+        .collect();
+
+    let continuity = match agent_log::place(&segment, &neighbours) {
+        Ok(Placement::New(continuity)) => continuity,
+        Ok(Placement::Duplicate) => {
+            let row = rows.iter().find(|r| r.get::<_, i64>(1) == index).ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+            let continuity: String = row.get(5);
+            return Ok(Json(LogSegmentReceipt {
+                segment_id: row.get(0),
+                chain_id: segment.chain_id,
+                segment_index: segment.segment_index,
+                continuity: Continuity::parse(&continuity).ok_or_else(|| {
+                    error!("FAIL-CLOSED: agent_log_segments has unknown continuity '{}'", continuity);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?,
+                duplicate: true,
+            }));
+        }
+        Err(reason) => {
+            warn!(
+                "Refused log segment {} of chain {} from agent {}: {}",
+                segment.segment_index, segment.chain_id, auth.component_identity, reason
+            );
+            http_agent_auth::audit(
+                state.store.as_ref(),
+                Some(auth.agent_id),
+                "AGENT_LOG_SEGMENT_REFUSED",
+                None,
+                &serde_json::json!({
+                    "agent_id": auth.agent_id.to_string(),
+                    "component_identity": auth.component_identity,
+                    "chain_id": segment.chain_id,
+                    "segment_index": segment.segment_index,
+                    "head_hash": segment.head_hash,
+                    "reason": reason,
+                }),
+            )
+            .await?;
+            return Err(StatusCode::CONFLICT);
+        }
+    };
+
+    // The spooled JSON is the lines plus a fixed-size envelope
+    let spool_bytes: u64 = segment.lines.iter().map(|l| l.len() as u64).sum();
+    state.disk_quotas.admit(disk_quota::AGENT_LOG_SPOOL, spool_bytes)?;
+
+    let entry = SpoolEntry {
+        segment_id: Uuid::new_v4(),
+        agent_id: auth.agent_id,
+        agent_component_id: auth.component_identity.clone(),
+        received_at: Utc::now(),
+        continuity,
+        segment,
+    };
+    // Spool first: a row only exists for a segment that is on disk
+    let spool_dir = config.spool_dir.clone();
+    let spooled = {
+        let (spool_dir, entry) = (spool_dir.clone(), entry.clone());
+        tokio::task::spawn_blocking(move || agent_log::write_spool(&spool_dir, &entry)).await
+    };
+    match spooled {
+        Ok(Ok(())) => state.disk_quotas.record_write(disk_quota::AGENT_LOG_SPOOL, spool_bytes),
+        Ok(Err(e)) => {
+            error!("FAIL-CLOSED: Failed to spool log segment {} in {}: {}", entry.segment_id, spool_dir.display(), e);
+            agent_log::remove_spool(&spool_dir, entry.segment_id);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+        Err(e) => {
+            error!("FAIL-CLOSED: log segment spool task failed for {}: {}", entry.segment_id, e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+
+    let segment = &entry.segment;
+    let decode = |h: &str| hex::decode(h).unwrap_or_default();
+    let signature = segment.signature_bytes().map_err(|_| StatusCode::BAD_REQUEST)?;
+    let inserted = db
+        .execute(
+            r#"
+            INSERT INTO agent_log_segments (
+              segment_id, agent_id, chain_id, boot_id, segment_index, first_line, line_count,
+              prev_hash, head_hash, sealed_at, segments_dropped_before, signer_key, signature,
+              continuity, received_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+            ON CONFLICT (agent_id, chain_id, segment_index) DO NOTHING
+            "#,
+            &[
+                &entry.segment_id,
+                &auth.agent_id,
+                &segment.chain_id,
+                &segment.boot_id,
+                &index,
+                &first_line,
+                &(segment.lines.len() as i32),
+                &decode(&segment.prev_hash),
+                &decode(&segment.head_hash),
+                &sealed_at,
+                &dropped_before,
+                &decode(&segment.signer_key),
+                &signature,
+                &continuity.as_str(),
+                &entry.received_at,
+            ],
+        )
+        .await;
+    match inserted {
+        Ok(1) => {}
+        Ok(_) => {
+            // The same index arrived concurrently; the agent resends and gets the stored outcome
+            agent_log::remove_spool(&spool_dir, entry.segment_id);
+            return Err(StatusCode::CONFLICT);
+        }
+        Err(e) => {
+            agent_log::remove_spool(&spool_dir, entry.segment_id);
+            return Err(db_err("Failed to record agent log segment")(e));
+        }
+    }
+
+    if continuity == Continuity::Gap || segment.segments_dropped_before > 0 {
+        http_agent_auth::audit(
+            state.store.as_ref(),
+            Some(auth.agent_id),
+            "AGENT_LOG_GAP",
+            Some(entry.segment_id),
+            &serde_json::json!({
+                "segment_id": entry.segment_id.to_string(),
+                "agent_id": auth.agent_id.to_string(),
+                "component_identity": auth.component_identity,
+                "chain_id": segment.chain_id,
+                "segment_index": segment.segment_index,
+                "continuity": continuity.as_str(),
+                "segments_dropped_before": segment.segments_dropped_before,
+            }),
+        )
+        .await?;
+        warn!(
+            "Log segment {} of chain {} from agent {} follows a gap (agent reported {} dropped)",
+            segment.segment_index, segment.chain_id, auth.component_identity, segment.segments_dropped_before
+        );
+    }
+    info!(
+        "Log segment stored | segment_id={} | agent={} | chain={} | index={} | lines={} | continuity={}",
+        entry.segment_id,
+        auth.component_identity,
+        segment.chain_id,
+        segment.segment_index,
+        segment.lines.len(),
+        continuity.as_str()
+    );
+    Ok(Json(LogSegmentReceipt {
+        segment_id: entry.segment_id,
+        chain_id: segment.chain_id.clone(),
+        segment_index: segment.segment_index,
+        continuity,
+        duplicate: false,
+    }))
+}
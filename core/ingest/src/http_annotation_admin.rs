@@ -0,0 +1,312 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/http_annotation_admin.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Authenticated admin endpoints for investigation annotations - add notes to incidents, detections and timeline entries, edit them as new revisions and list them with their history (all audited)
+
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use crypto::digest::Sha256;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::error::SqlState;
+use tokio_postgres::Client;
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::annotation::{self, Annotation, AnnotationRevision, AnnotationSubject};
+use crate::http_agent_auth;
+use crate::http_list::{ListQuery, ListQueryRejection, ListSpec, Page};
+use crate::http_server::AppState;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateAnnotationRequest {
+    pub subject_type: AnnotationSubject,
+    /// Incident or detection UUID, or timeline entry id
+    pub subject_id: String,
+    pub author: String,
+    pub body: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct EditAnnotationRequest {
+    pub annotation_id: Uuid,
+    /// Revision being replaced (the current one)
+    pub revision: i32,
+    pub author: String,
+    pub body: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AnnotationChangeResponse {
+    pub annotation_id: String,
+    /// Revision written
+    pub revision: i32,
+}
+
+const ANNOTATION_LIST: ListSpec = ListSpec {
+    filterable: &["subject_type", "subject_id", "created_by", "created_at", "author", "edited_at", "revision"],
+    selectable: &[
+        "annotation_id",
+        "subject_type",
+        "subject_id",
+        "created_by",
+        "created_at",
+        "revision",
+        "body",
+        "author",
+        "edited_at",
+        "revisions",
+    ],
+};
+
+fn control_db(state: &AppState) -> Result<&Arc<Client>, StatusCode> {
+    state.db.as_ref().ok_or_else(|| {
+        error!("Annotation operation requested but storage backend has no control plane (postgres required)");
+        StatusCode::SERVICE_UNAVAILABLE
+    })
+}
+
+fn db_err(what: &str) -> impl FnOnce(tokio_postgres::Error) -> StatusCode + '_ {
+    move |e| {
+        error!("FAIL-CLOSED: {}: {}", what, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+fn bad_request(e: String) -> StatusCode {
+    warn!("Rejected annotation: {}", e);
+    StatusCode::BAD_REQUEST
+}
+
+fn micros_key(at: DateTime<Utc>, id: Uuid) -> String {
+    format!("{}|{}", at.to_rfc3339_opts(SecondsFormat::Micros, true), id)
+}
+
+/// POST /admin/annotations (X-Admin-Key): add a note to an incident, detection or timeline entry.
+#[utoipa::path(
+    post,
+    path = "/admin/annotations",
+    tag = "admin",
+    request_body = CreateAnnotationRequest,
+    responses(
+        (status = 200, description = "Note added as revision 1", body = AnnotationChangeResponse),
+        (status = 400, description = "Invalid subject_id, empty author or body, body too long"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_create_annotation(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<CreateAnnotationRequest>,
+) -> Result<Json<AnnotationChangeResponse>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    let subject_id = req.subject_type.normalize_id(&req.subject_id).map_err(bad_request)?;
+    annotation::check_field("author", &req.author).map_err(bad_request)?;
+    annotation::check_body(&req.body).map_err(bad_request)?;
+    let db = control_db(&state)?;
+    let annotation_id = Uuid::new_v4();
+    // One statement: the annotation never exists without its first revision
+    db.execute(
+        r#"
+        WITH created AS (
+            INSERT INTO annotations (annotation_id, subject_type, subject_id, created_by)
+            VALUES ($1, $2, $3, $4)
+            RETURNING annotation_id, created_at
+        )
+        INSERT INTO annotation_revisions (annotation_id, revision, body, author, edited_at)
+        SELECT annotation_id, 1, $5, $4, created_at FROM created
+        "#,
+        &[&annotation_id, &req.subject_type.as_str(), &subject_id, &req.author, &req.body],
+    )
+    .await
+    .map_err(db_err("Failed to insert annotation"))?;
+
+    http_agent_auth::audit(
+        state.store.as_ref(),
+        None,
+        "ANNOTATION_CREATED",
+        Some(annotation_id),
+        &serde_json::json!({
+            "annotation_id": annotation_id.to_string(),
+            "subject_type": req.subject_type.as_str(),
+            "subject_id": subject_id,
+            "author": req.author,
+            "revision": 1,
+            "body_sha256": hex::encode(Sha256::digest(req.body.as_bytes())),
+        }),
+    )
+    .await?;
+    info!(
+        "Annotation added | annotation_id={} | {}={} | by={}",
+        annotation_id,
+        req.subject_type.as_str(),
+        subject_id,
+        req.author
+    );
+    Ok(Json(AnnotationChangeResponse { annotation_id: annotation_id.to_string(), revision: 1 }))
+}
+
+/// POST /admin/annotations/edit (X-Admin-Key): replace the current text of a note with a new
+/// revision; earlier revisions are kept.
+#[utoipa::path(
+    post,
+    path = "/admin/annotations/edit",
+    tag = "admin",
+    request_body = EditAnnotationRequest,
+    responses(
+        (status = 200, description = "Revision appended", body = AnnotationChangeResponse),
+        (status = 400, description = "Empty author, body or reason, body too long"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 404, description = "No annotation with this id and revision"),
+        (status = 409, description = "The revision was already edited (reload and retry)"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_edit_annotation(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<EditAnnotationRequest>,
+) -> Result<Json<AnnotationChangeResponse>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    annotation::check_field("author", &req.author).map_err(bad_request)?;
+    annotation::check_field("reason", &req.reason).map_err(bad_request)?;
+    annotation::check_body(&req.body).map_err(bad_request)?;
+    let revision = req.revision.checked_add(1).ok_or(StatusCode::BAD_REQUEST)?;
+    // (annotation_id, revision) is the key: a concurrent edit of the same revision conflicts
+    let inserted = control_db(&state)?
+        .execute(
+            r#"
+            INSERT INTO annotation_revisions (annotation_id, revision, body, author, edit_reason)
+            SELECT annotation_id, $2, $3, $4, $5
+            FROM annotation_revisions
+            WHERE annotation_id = $1 AND revision = $6
+            "#,
+            &[&req.annotation_id, &revision, &req.body, &req.author, &req.reason, &req.revision],
+        )
+        .await;
+    match inserted {
+        Ok(0) => return Err(StatusCode::NOT_FOUND),
+        Ok(_) => {}
+        Err(e) if e.code() == Some(&SqlState::UNIQUE_VIOLATION) => {
+            warn!(
+                "Rejected annotation edit: {} revision {} was already edited",
+                req.annotation_id, req.revision
+            );
+            return Err(StatusCode::CONFLICT);
+        }
+        Err(e) => return Err(db_err("Failed to insert annotation revision")(e)),
+    }
+
+    http_agent_auth::audit(
+        state.store.as_ref(),
+        None,
+        "ANNOTATION_EDITED",
+        Some(req.annotation_id),
+        &serde_json::json!({
+            "annotation_id": req.annotation_id.to_string(),
+            "author": req.author,
+            "revision": revision,
+            "reason": req.reason,
+            "body_sha256": hex::encode(Sha256::digest(req.body.as_bytes())),
+        }),
+    )
+    .await?;
+    info!(
+        "Annotation edited | annotation_id={} | revision={} | by={}",
+        req.annotation_id, revision, req.author
+    );
+    Ok(Json(AnnotationChangeResponse { annotation_id: req.annotation_id.to_string(), revision }))
+}
+
+/// GET /admin/annotations (X-Admin-Key): notes with their revision history, oldest first, as a
+/// list page. filter[subject_id]=<incident> selects an incident's notes; the page is also the
+/// export the reporting pipeline includes in forensic reports.
+#[utoipa::path(
+    get,
+    path = "/admin/annotations",
+    tag = "admin",
+    params(
+        ("limit" = Option<usize>, Query, description = "Page size (1-500, default 50)"),
+        ("cursor" = Option<String>, Query, description = "next_cursor of the previous page"),
+        ("fields" = Option<String>, Query, description = "Comma-separated sparse fieldset"),
+    ),
+    responses(
+        (status = 200, description = "Page of notes (Annotation items)", body = Page),
+        (status = 400, description = "Unsupported list parameter"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_list_annotations(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<ListQuery, ListQueryRejection>,
+) -> Result<Json<Page>, Response> {
+    state.admin_key.check(&headers).map_err(IntoResponse::into_response)?;
+    let query = query.map_err(IntoResponse::into_response)?;
+    let db = control_db(&state).map_err(IntoResponse::into_response)?;
+    let rows = db
+        .query(
+            r#"
+            SELECT a.annotation_id, a.subject_type, a.subject_id, a.created_by, a.created_at,
+                   r.revision, r.body, r.author, r.edited_at, r.edit_reason
+            FROM annotations a
+            JOIN annotation_revisions r ON r.annotation_id = a.annotation_id
+            ORDER BY a.annotation_id, r.revision
+            "#,
+            &[],
+        )
+        .await
+        .map_err(db_err("Failed to list annotations"))
+        .map_err(IntoResponse::into_response)?;
+    let mut annotations: Vec<Annotation> = Vec::new();
+    for r in &rows {
+        let annotation_id: Uuid = r.get(0);
+        let revision = AnnotationRevision {
+            revision: r.get(5),
+            body: r.get(6),
+            author: r.get(7),
+            edited_at: r.get(8),
+            edit_reason: r.get(9),
+        };
+        if let Some(current) = annotations.last_mut().filter(|a| a.annotation_id == annotation_id) {
+            current.revision = revision.revision;
+            current.body = revision.body.clone();
+            current.author = revision.author.clone();
+            current.edited_at = revision.edited_at;
+            current.revisions.push(revision);
+            continue;
+        }
+        let subject_type: String = r.get(1);
+        let subject_type = AnnotationSubject::parse(&subject_type).ok_or_else(|| {
+            error!("FAIL-CLOSED: annotations has unknown subject_type '{}'", subject_type);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        })?;
+        annotations.push(Annotation {
+            annotation_id,
+            subject_type,
+            subject_id: r.get(2),
+            created_by: r.get(3),
+            created_at: r.get(4),
+            revision: revision.revision,
+            body: revision.body.clone(),
+            author: revision.author.clone(),
+            edited_at: revision.edited_at,
+            revisions: vec![revision],
+        });
+    }
+    query
+        .paginate(&ANNOTATION_LIST, annotations, |a| micros_key(a.created_at, a.annotation_id))
+        .map(Json)
+        .map_err(IntoResponse::into_response)
+}
@@ -0,0 +1,337 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/http_audit_review_admin.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Authenticated admin endpoints for audit log review - attest to a verified immutable_audit_log range (appended to the chain), list attestations and report the reviewed/unreviewed coverage of a period
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+    Extension,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Deserialize;
+use tokio_postgres::{Client, Row};
+use tracing::{error, info, warn};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::audit_review::{
+    self, AttestationPayload, AuditReviewAttestation, AuditReviewCoverage, ChainLink, RangeVerifier, ReviewerAuth,
+};
+use crate::http_agent_auth;
+use crate::http_list::{ListQuery, ListQueryRejection, ListSpec, Page};
+use crate::http_server::AppState;
+use crate::operator_auth::{OperatorAuthMethod, OperatorIdentity};
+
+/// Audit entries verified per query
+const VERIFY_PAGE: i64 = 10_000;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AuditReviewRequest {
+    pub range_start: DateTime<Utc>,
+    /// Exclusive; at least 60s in the past
+    pub range_end: DateTime<Utc>,
+    /// Required with X-Admin-Key; taken from the session for operators signed in through the IdP
+    pub reviewer: Option<String>,
+    /// Hex root hash the reviewer saw; the attestation is refused if the chain no longer matches
+    pub root_hash: Option<String>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct AuditReviewCoverageQuery {
+    pub from: DateTime<Utc>,
+    /// Exclusive
+    pub to: DateTime<Utc>,
+}
+
+const ATTESTATION_LIST: ListSpec = ListSpec {
+    filterable: &["reviewer", "authenticated_by", "attested_at", "range_start", "range_end", "root_hash"],
+    selectable: &[
+        "attestation_id",
+        "attested_at",
+        "reviewer",
+        "authenticated_by",
+        "range_start",
+        "range_end",
+        "entry_count",
+        "first_audit_id",
+        "last_audit_id",
+        "root_hash",
+        "notes",
+    ],
+};
+
+fn control_db(state: &AppState) -> Result<&Arc<Client>, StatusCode> {
+    state.db.as_ref().ok_or_else(|| {
+        error!("Audit review requested but storage backend has no control plane (postgres required)");
+        StatusCode::SERVICE_UNAVAILABLE
+    })
+}
+
+fn db_err(what: &str) -> impl FnOnce(tokio_postgres::Error) -> StatusCode + '_ {
+    move |e| {
+        error!("FAIL-CLOSED: {}: {}", what, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+fn bad_request(e: String) -> StatusCode {
+    warn!("Rejected audit review: {}", e);
+    StatusCode::BAD_REQUEST
+}
+
+fn chain_link(r: &Row) -> ChainLink {
+    ChainLink {
+        audit_id: r.get(0),
+        created_at: r.get(1),
+        prev_audit_id: r.get(2),
+        payload_sha256: r.get(3),
+        chain_hash_sha256: r.get(4),
+    }
+}
+
+/// Verify the chain from the entry before `start` through the last entry before `end`.
+async fn verify_range(
+    db: &Client,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Result<audit_review::RangeDigest, String>, StatusCode> {
+    let predecessor = db
+        .query_opt(
+            r#"
+            SELECT audit_id, created_at, prev_audit_id, payload_sha256, chain_hash_sha256
+            FROM immutable_audit_log
+            WHERE created_at < $1
+            ORDER BY created_at DESC, audit_id DESC
+            LIMIT 1
+            "#,
+            &[&start],
+        )
+        .await
+        .map_err(db_err("Failed to read audit chain"))?
+        .map(|r| chain_link(&r));
+    let mut verifier = RangeVerifier::new(predecessor.as_ref());
+    // Keyset pages; no audit_id is nil, so (start, nil) precedes every entry of the range
+    let mut after = (start, Uuid::nil());
+    loop {
+        let rows = db
+            .query(
+                r#"
+                SELECT audit_id, created_at, prev_audit_id, payload_sha256, chain_hash_sha256
+                FROM immutable_audit_log
+                WHERE (created_at, audit_id) > ($1, $2) AND created_at < $3
+                ORDER BY created_at, audit_id
+                LIMIT $4
+                "#,
+                &[&after.0, &after.1, &end, &VERIFY_PAGE],
+            )
+            .await
+            .map_err(db_err("Failed to read audit chain"))?;
+        for r in &rows {
+            let link = chain_link(r);
+            if let Err(e) = verifier.push(&link) {
+                return Ok(Err(e));
+            }
+            after = (link.created_at, link.audit_id);
+        }
+        if (rows.len() as i64) < VERIFY_PAGE {
+            return Ok(Ok(verifier.finish()));
+        }
+    }
+}
+
+async fn load_attestations(db: &Client) -> Result<Vec<AuditReviewAttestation>, StatusCode> {
+    let rows = db
+        .query(
+            r#"
+            SELECT audit_id, created_at, payload_json
+            FROM immutable_audit_log
+            WHERE action = $1
+            ORDER BY created_at, audit_id
+            "#,
+            &[&audit_review::ATTESTATION_ACTION],
+        )
+        .await
+        .map_err(db_err("Failed to list audit review attestations"))?;
+    let mut attestations = Vec::with_capacity(rows.len());
+    for r in &rows {
+        let audit_id: Uuid = r.get(0);
+        let payload: Option<serde_json::Value> = r.get(2);
+        let payload: AttestationPayload =
+            serde_json::from_value(payload.unwrap_or_default()).map_err(|e| {
+                error!("FAIL-CLOSED: attestation {} has an unreadable payload: {}", audit_id, e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+        attestations.push(AuditReviewAttestation::from_entry(audit_id, r.get(1), payload));
+    }
+    Ok(attestations)
+}
+
+/// POST /admin/audit-reviews (X-Admin-Key): attest to having reviewed the audit entries created
+/// in [range_start, range_end). The range is verified link by link and the attestation, with the
+/// range's root hash, is appended to the audit chain. Operators signed in through the IdP attest
+/// as themselves; break-glass sessions cannot attest.
+#[utoipa::path(
+    post,
+    path = "/admin/audit-reviews",
+    tag = "admin",
+    request_body = AuditReviewRequest,
+    responses(
+        (status = 200, description = "Attestation appended to the audit chain", body = AuditReviewAttestation),
+        (status = 400, description = "Invalid or unsettled range, missing reviewer, notes too long"),
+        (status = 401, description = "Invalid admin key or operator token"),
+        (status = 403, description = "Not an authorized reviewer, or a break-glass session"),
+        (status = 409, description = "The chain does not verify over the range, or root_hash does not match"),
+        (status = 503, description = "No reviewers, admin key or postgres control plane configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_attest_review(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    operator: Option<Extension<OperatorIdentity>>,
+    Json(req): Json<AuditReviewRequest>,
+) -> Result<Json<AuditReviewAttestation>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    if state.audit_reviewers.is_empty() {
+        warn!("Audit review attempted but RANSOMEYE_AUDIT_REVIEWERS is not configured");
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+    let (reviewer, authenticated_by) = match operator {
+        Some(Extension(identity)) => {
+            if identity.method == OperatorAuthMethod::BreakGlass {
+                warn!("Audit review by break-glass session '{}' refused", identity.subject);
+                return Err(StatusCode::FORBIDDEN);
+            }
+            if req.reviewer.as_ref().is_some_and(|r| *r != identity.name && *r != identity.subject) {
+                return Err(bad_request(format!("reviewer does not match the signed-in operator '{}'", identity.name)));
+            }
+            let reviewer = if state.audit_reviewers.contains(&identity.subject) { identity.subject } else { identity.name };
+            (reviewer, ReviewerAuth::Oidc)
+        }
+        None => {
+            let reviewer = req.reviewer.clone().ok_or_else(|| bad_request("reviewer is required".to_string()))?;
+            (reviewer, ReviewerAuth::AdminKey)
+        }
+    };
+    audit_review::check_reviewer(&reviewer).map_err(bad_request)?;
+    if !state.audit_reviewers.contains(&reviewer) {
+        warn!("Audit review by '{}' refused: not in RANSOMEYE_AUDIT_REVIEWERS", reviewer);
+        return Err(StatusCode::FORBIDDEN);
+    }
+    audit_review::check_range(req.range_start, req.range_end, Utc::now()).map_err(bad_request)?;
+    if req.notes.as_ref().is_some_and(|n| n.len() > audit_review::MAX_NOTES_BYTES) {
+        return Err(bad_request(format!("notes exceed {} bytes", audit_review::MAX_NOTES_BYTES)));
+    }
+
+    let db = control_db(&state)?;
+    let digest = match verify_range(db, req.range_start, req.range_end).await? {
+        Ok(digest) => digest,
+        Err(e) => {
+            error!("AUDIT CHAIN BROKEN in review range {} - {}: {}", req.range_start, req.range_end, e);
+            return Err(StatusCode::CONFLICT);
+        }
+    };
+    if let Some(expected) = &req.root_hash {
+        if !expected.eq_ignore_ascii_case(&digest.root_hash) {
+            warn!("Audit review refused: root hash {} was reviewed, the chain has {}", expected, digest.root_hash);
+            return Err(StatusCode::CONFLICT);
+        }
+    }
+
+    let payload = AttestationPayload {
+        reviewer,
+        authenticated_by,
+        range_start: req.range_start,
+        range_end: req.range_end,
+        entry_count: digest.entry_count,
+        first_audit_id: digest.first_audit_id,
+        last_audit_id: digest.last_audit_id,
+        root_hash: digest.root_hash,
+        notes: req.notes,
+    };
+    let payload_json = serde_json::to_value(&payload).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let attestation_id =
+        http_agent_auth::audit(state.store.as_ref(), None, audit_review::ATTESTATION_ACTION, None, &payload_json).await?;
+    // The chain row is the attestation; its created_at is the attestation time
+    let attested_at: DateTime<Utc> = db
+        .query_one("SELECT created_at FROM immutable_audit_log WHERE audit_id = $1", &[&attestation_id])
+        .await
+        .map_err(db_err("Failed to read back audit review attestation"))?
+        .get(0);
+    info!(
+        "Audit review attested | reviewer={} | range={} - {} | entries={} | root={}",
+        payload.reviewer,
+        payload.range_start.to_rfc3339_opts(SecondsFormat::Secs, true),
+        payload.range_end.to_rfc3339_opts(SecondsFormat::Secs, true),
+        payload.entry_count,
+        payload.root_hash
+    );
+    Ok(Json(AuditReviewAttestation::from_entry(attestation_id, attested_at, payload)))
+}
+
+/// GET /admin/audit-reviews (X-Admin-Key): audit review attestations, oldest first, as a list page.
+#[utoipa::path(
+    get,
+    path = "/admin/audit-reviews",
+    tag = "admin",
+    params(
+        ("limit" = Option<usize>, Query, description = "Page size (1-500, default 50)"),
+        ("cursor" = Option<String>, Query, description = "next_cursor of the previous page"),
+        ("fields" = Option<String>, Query, description = "Comma-separated sparse fieldset"),
+    ),
+    responses(
+        (status = 200, description = "Page of attestations (AuditReviewAttestation items)", body = Page),
+        (status = 400, description = "Unsupported list parameter"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_list_reviews(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<ListQuery, ListQueryRejection>,
+) -> Result<Json<Page>, Response> {
+    state.admin_key.check(&headers).map_err(IntoResponse::into_response)?;
+    let query = query.map_err(IntoResponse::into_response)?;
+    let db = control_db(&state).map_err(IntoResponse::into_response)?;
+    let attestations = load_attestations(db).await.map_err(IntoResponse::into_response)?;
+    query
+        .paginate(&ATTESTATION_LIST, attestations, |a| {
+            format!("{}|{}", a.attested_at.to_rfc3339_opts(SecondsFormat::Micros, true), a.attestation_id)
+        })
+        .map(Json)
+        .map_err(IntoResponse::into_response)
+}
+
+/// GET /admin/audit-reviews/coverage (X-Admin-Key): which parts of [from, to) are covered by
+/// attestations and which are not, with the attestations involved - the compliance report.
+#[utoipa::path(
+    get,
+    path = "/admin/audit-reviews/coverage",
+    tag = "admin",
+    params(AuditReviewCoverageQuery),
+    responses(
+        (status = 200, description = "Reviewed and unreviewed periods", body = AuditReviewCoverage),
+        (status = 400, description = "from is not before to"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_review_coverage(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<AuditReviewCoverageQuery>,
+) -> Result<Json<AuditReviewCoverage>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    if query.from >= query.to {
+        return Err(bad_request("from must be before to".to_string()));
+    }
+    let attestations = load_attestations(control_db(&state)?).await?;
+    Ok(Json(audit_review::coverage(query.from, query.to, attestations)))
+}
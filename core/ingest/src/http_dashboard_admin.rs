@@ -0,0 +1,121 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/http_dashboard_admin.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Authenticated admin endpoints for the dashboard - detections per severity, events per minute and fleet summary served through the TTL cache, and the cache's hit/miss counters
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use tracing::error;
+
+use crate::dashboard_cache::{DashboardCacheStats, DetectionsBySeverity, EventsPerMinute, FleetOverview};
+use crate::http_server::AppState;
+use crate::storage::StorageError;
+
+fn query_failed(query: &str, e: StorageError) -> StatusCode {
+    error!("Dashboard query {} failed: {}", query, e);
+    StatusCode::INTERNAL_SERVER_ERROR
+}
+
+/// GET /admin/dashboard/detections-by-severity (X-Admin-Key): unsuppressed detections of the last
+/// 24 hours per severity, at most RANSOMEYE_INGEST_DASHBOARD_CACHE_TTL_SECS old.
+#[utoipa::path(
+    get,
+    path = "/admin/dashboard/detections-by-severity",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Detections per severity, most severe first", body = DetectionsBySeverity),
+        (status = 401, description = "Invalid admin key"),
+        (status = 500, description = "Store query failed"),
+        (status = 503, description = "RANSOMEYE_ADMIN_KEY_PATH not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_detections_by_severity(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<DetectionsBySeverity>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    state
+        .dashboard
+        .detections_by_severity(state.store.as_ref())
+        .await
+        .map(Json)
+        .map_err(|e| query_failed("detections_by_severity", e))
+}
+
+/// GET /admin/dashboard/events-per-minute (X-Admin-Key): raw events received per minute over the
+/// last hour, at most RANSOMEYE_INGEST_DASHBOARD_CACHE_TTL_SECS old.
+#[utoipa::path(
+    get,
+    path = "/admin/dashboard/events-per-minute",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Events per minute, oldest first (minutes without events omitted)", body = EventsPerMinute),
+        (status = 401, description = "Invalid admin key"),
+        (status = 500, description = "Store query failed"),
+        (status = 503, description = "RANSOMEYE_ADMIN_KEY_PATH not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_events_per_minute(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<EventsPerMinute>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    state
+        .dashboard
+        .events_per_minute(state.store.as_ref())
+        .await
+        .map(Json)
+        .map_err(|e| query_failed("events_per_minute", e))
+}
+
+/// GET /admin/dashboard/fleet-summary (X-Admin-Key): agents per type, decommissioned and
+/// reporting in the last 15 minutes, at most RANSOMEYE_INGEST_DASHBOARD_CACHE_TTL_SECS old.
+#[utoipa::path(
+    get,
+    path = "/admin/dashboard/fleet-summary",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Fleet agent counts", body = FleetOverview),
+        (status = 401, description = "Invalid admin key"),
+        (status = 500, description = "Store query failed"),
+        (status = 503, description = "RANSOMEYE_ADMIN_KEY_PATH not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_fleet_summary(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<FleetOverview>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    state
+        .dashboard
+        .fleet_summary(state.store.as_ref())
+        .await
+        .map(Json)
+        .map_err(|e| query_failed("fleet_summary", e))
+}
+
+/// GET /admin/dashboard/cache (X-Admin-Key): hits, misses and invalidations of each cached
+/// dashboard query on this instance since start.
+#[utoipa::path(
+    get,
+    path = "/admin/dashboard/cache",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Dashboard cache counters", body = DashboardCacheStats),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "RANSOMEYE_ADMIN_KEY_PATH not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_get_cache_stats(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<DashboardCacheStats>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    Ok(Json(state.dashboard.stats()))
+}
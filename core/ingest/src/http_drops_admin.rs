@@ -0,0 +1,90 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/http_drops_admin.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Authenticated admin endpoints for dropped-event accounting - daily drops per agent, origin and reason, and this instance's ingest-side drop totals
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use chrono::{Datelike, Duration, Utc};
+use tracing::error;
+
+use crate::drop_accounting::IngestDropStats;
+use crate::http_list::{ListQuery, ListQueryRejection, ListSpec, Page};
+use crate::http_server::AppState;
+
+/// Days of telemetry_drops_daily served per query (older rows stay in the table for SQL access).
+const DROP_SUMMARY_DAYS: i64 = 90;
+const DROP_SUMMARY_WINDOW: i64 = 10_000;
+
+const DROP_LIST: ListSpec = ListSpec {
+    filterable: &["agent_id", "day", "origin", "reason", "dropped"],
+    selectable: &["agent_id", "day", "origin", "reason", "dropped", "last_recorded_at"],
+};
+
+/// GET /admin/drops (X-Admin-Key): dropped events per agent, day, origin and reason, newest day
+/// first, as a list page. filter[agent_id]=... gives one agent's completeness.
+#[utoipa::path(
+    get,
+    path = "/admin/drops",
+    tag = "admin",
+    params(
+        ("limit" = Option<usize>, Query, description = "Page size (1-500, default 50)"),
+        ("cursor" = Option<String>, Query, description = "next_cursor of the previous page"),
+        ("fields" = Option<String>, Query, description = "Comma-separated sparse fieldset"),
+    ),
+    responses(
+        (status = 200, description = "Page of daily drop counts (DropSummary items, last 90 days)", body = Page),
+        (status = 400, description = "Unsupported list parameter"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "RANSOMEYE_ADMIN_KEY_PATH not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_list_drops(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<ListQuery, ListQueryRejection>,
+) -> Result<Json<Page>, Response> {
+    state.admin_key.check(&headers).map_err(IntoResponse::into_response)?;
+    let query = query.map_err(IntoResponse::into_response)?;
+    let since = (Utc::now() - Duration::days(DROP_SUMMARY_DAYS)).date_naive();
+    let drops = state.store.drop_summary(since, DROP_SUMMARY_WINDOW).await.map_err(|e| {
+        error!("Failed to read telemetry_drops_daily: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR.into_response()
+    })?;
+    // Newest day first: invert the day so ascending key order is descending time
+    query
+        .paginate(&DROP_LIST, drops, |d| {
+            format!(
+                "{:010}|{}|{}|{}",
+                i32::MAX - d.day.num_days_from_ce(),
+                d.agent_id,
+                d.origin.as_str(),
+                d.reason
+            )
+        })
+        .map(Json)
+        .map_err(IntoResponse::into_response)
+}
+
+/// GET /admin/drops/ingest (X-Admin-Key): requests this instance refused since start, per reason.
+#[utoipa::path(
+    get,
+    path = "/admin/drops/ingest",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Ingest-side drop totals of this instance", body = IngestDropStats),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "RANSOMEYE_ADMIN_KEY_PATH not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_get_ingest_drops(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<IngestDropStats>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    Ok(Json(state.drops.stats()))
+}
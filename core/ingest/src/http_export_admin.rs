@@ -0,0 +1,189 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/http_export_admin.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Authenticated admin endpoints for bulk data export - NDJSON stream of normalized events with resumable cursors (one audit record per export session) and export counters
+
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use chrono::Utc;
+use tokio::sync::mpsc;
+use tokio_postgres::Client;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{error, info, warn};
+
+use crate::export::{self, ExportCursor, ExportLine, ExportParams, ExportPermit, ExportRefusal, ExportStats, EXPORT_BATCH};
+use crate::http_agent_auth;
+use crate::http_server::AppState;
+
+/// Lines buffered ahead of a slow client
+const EXPORT_STREAM_BUFFER: usize = 4;
+
+fn bad_request(reason: String) -> Response {
+    (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": reason }))).into_response()
+}
+
+/// GET /admin/export/events (X-Admin-Key): stream normalized events as NDJSON. Start a session
+/// with since (and optionally until, event_kind, tenant) plus requested_by; resume it with the
+/// cursor of the last event line processed.
+#[utoipa::path(
+    get,
+    path = "/admin/export/events",
+    tag = "admin",
+    params(
+        ("since" = Option<String>, Query, description = "RFC 3339 start of the normalized_at window, inclusive (new session)"),
+        ("until" = Option<String>, Query, description = "RFC 3339 end of the window, exclusive; default and maximum now (new session)"),
+        ("event_kind" = Option<String>, Query, description = "Comma-separated event kinds (new session)"),
+        ("tenant" = Option<String>, Query, description = "Tenant of the source agent (new session)"),
+        ("cursor" = Option<String>, Query, description = "Resume after this event line; excludes the filter parameters"),
+        ("limit" = Option<u64>, Query, description = "Event lines in this response (1-1000000, default 100000)"),
+        ("requested_by" = Option<String>, Query, description = "Operator starting the session (required for a new session; audited)"),
+    ),
+    responses(
+        (status = 200, description = "NDJSON: event lines {type, cursor, event: ExportedEvent}, then {type: end, next_cursor, exported}", content_type = "application/x-ndjson"),
+        (status = 400, description = "Invalid parameter or cursor"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 429, description = "Too many concurrent exports or export requests this minute (Retry-After)"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_export_events(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    params: Result<Query<ExportParams>, axum::extract::rejection::QueryRejection>,
+) -> Result<Response, Response> {
+    state.admin_key.check(&headers).map_err(IntoResponse::into_response)?;
+    let Query(params) = params.map_err(|e| bad_request(e.body_text()))?;
+    let start = params.start(Utc::now()).map_err(bad_request)?;
+    let db = state.db.clone().ok_or_else(|| {
+        error!("Data export requested but storage backend has no control plane (postgres required)");
+        StatusCode::SERVICE_UNAVAILABLE.into_response()
+    })?;
+
+    let permit = state.export.acquire().map_err(|refusal| {
+        let retry_after = match refusal {
+            ExportRefusal::TooManyConcurrent => 30,
+            ExportRefusal::RateLimited { retry_after_secs } => retry_after_secs,
+        };
+        warn!("Data export refused: {:?}", refusal);
+        (StatusCode::TOO_MANY_REQUESTS, [(header::RETRY_AFTER, retry_after.to_string())]).into_response()
+    })?;
+
+    let cursor = start.cursor;
+    let f = &cursor.filter;
+    if start.new_session {
+        // One audit record per session, written before any row leaves the system
+        http_agent_auth::audit(
+            state.store.as_ref(),
+            None,
+            "DATA_EXPORT_SESSION_STARTED",
+            Some(cursor.session_id),
+            &serde_json::json!({
+                "session_id": cursor.session_id.to_string(),
+                "requested_by": params.requested_by,
+                "since": f.since.to_rfc3339(),
+                "until": f.until.to_rfc3339(),
+                "event_kinds": f.event_kinds,
+                "tenant": f.tenant,
+            }),
+        )
+        .await
+        .map_err(IntoResponse::into_response)?;
+        state.export.session_started();
+        info!(
+            "Data export session started | session={} | by={} | window={}..{} | kinds={:?} | tenant={:?}",
+            cursor.session_id,
+            params.requested_by.as_deref().unwrap_or_default(),
+            f.since.to_rfc3339(),
+            f.until.to_rfc3339(),
+            f.event_kinds,
+            f.tenant
+        );
+    } else {
+        info!("Data export session resumed | session={} | after={:?}", cursor.session_id, cursor.after);
+    }
+
+    let session_id = cursor.session_id.to_string();
+    let (tx, rx) = mpsc::channel::<Result<String, std::io::Error>>(EXPORT_STREAM_BUFFER);
+    tokio::spawn(stream_export(db, cursor, start.limit, permit, tx));
+
+    let mut response = Body::from_stream(ReceiverStream::new(rx)).into_response();
+    let h = response.headers_mut();
+    h.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/x-ndjson"));
+    if let Ok(v) = HeaderValue::from_str(&session_id) {
+        h.insert("x-export-session", v);
+    }
+    Ok(response)
+}
+
+/// Produce the lines of one response. A read error ends the body without an end line, so the
+/// client resumes from its last cursor; a closed client ends the stream (and frees the permit).
+async fn stream_export(
+    db: Arc<Client>,
+    mut cursor: ExportCursor,
+    limit: u64,
+    permit: ExportPermit,
+    tx: mpsc::Sender<Result<String, std::io::Error>>,
+) {
+    let mut exported = 0u64;
+    let exhausted = loop {
+        let want = (limit - exported).min(EXPORT_BATCH as u64) as i64;
+        let batch = match export::fetch_batch(&db, &cursor, want).await {
+            Ok(batch) => batch,
+            Err(e) => {
+                error!("Data export session {} failed: {}", cursor.session_id, e);
+                let _ = tx.send(Err(std::io::Error::other(e))).await;
+                return;
+            }
+        };
+        let rows = batch.len() as u64;
+        for event in batch {
+            cursor = cursor.after(event.normalized_at, event.normalized_event_id);
+            let line = ExportLine::Event { cursor: cursor.encode(), event: Box::new(event) };
+            if tx.send(Ok(line.to_ndjson())).await.is_err() {
+                info!("Data export session {} client disconnected after {} event(s)", cursor.session_id, exported);
+                permit.count_rows(exported);
+                return;
+            }
+            exported += 1;
+        }
+        if rows < want as u64 {
+            break true;
+        }
+        if exported >= limit {
+            break false;
+        }
+    };
+    permit.count_rows(exported);
+    let end = ExportLine::End { next_cursor: (!exhausted).then(|| cursor.encode()), exported };
+    let _ = tx.send(Ok(end.to_ndjson())).await;
+    info!(
+        "Data export session {} response complete | exported={} | exhausted={}",
+        cursor.session_id, exported, exhausted
+    );
+}
+
+/// GET /admin/export/stats (X-Admin-Key): export streams and counters of this instance.
+#[utoipa::path(
+    get,
+    path = "/admin/export/stats",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Active export streams, sessions started, rows exported and refused requests", body = ExportStats),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "RANSOMEYE_ADMIN_KEY_PATH not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_get_export_stats(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<ExportStats>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    Ok(Json(state.export.stats()))
+}
@@ -0,0 +1,293 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/http_feature_flags_admin.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Authenticated admin endpoints for runtime feature flags - list effective values, set a global value or tenant override, and clear it (all audited, applied without a restart)
+
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+
+use crate::feature_flags::{self, Flag, FlagSetting, FlagSource};
+use crate::http_agent_auth;
+use crate::http_server::AppState;
+
+/// One flag: its default, the rows that set it and what applies now.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FeatureFlagStatus {
+    pub flag: String,
+    pub description: String,
+    pub default_enabled: bool,
+    /// Global row, if any
+    pub global: Option<FlagSetting>,
+    /// Tenant overrides, by tenant_id
+    pub overrides: Vec<FlagSetting>,
+    /// Value for agents without an override, as this instance applies it
+    pub enabled: bool,
+    pub source: FlagSource,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FeatureFlagList {
+    pub flags: Vec<FeatureFlagStatus>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetFeatureFlagRequest {
+    pub flag: String,
+    /// Absent: the global value
+    pub tenant_id: Option<String>,
+    pub enabled: bool,
+    pub changed_by: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SetFeatureFlagResponse {
+    pub flag: String,
+    pub tenant_id: Option<String>,
+    pub enabled: bool,
+    /// Value of the row this replaced (None: there was no row)
+    pub previous: Option<bool>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ClearFeatureFlagRequest {
+    pub flag: String,
+    /// Absent: the global value
+    pub tenant_id: Option<String>,
+    pub cleared_by: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ClearFeatureFlagResponse {
+    pub flag: String,
+    pub tenant_id: Option<String>,
+    /// Value of the removed row
+    pub previous: bool,
+}
+
+fn control_db(state: &AppState) -> Result<&Arc<Client>, StatusCode> {
+    state.db.as_ref().ok_or_else(|| {
+        error!("Feature flag operation requested but storage backend has no control plane (postgres required)");
+        StatusCode::SERVICE_UNAVAILABLE
+    })
+}
+
+fn db_err(what: &str) -> impl FnOnce(tokio_postgres::Error) -> StatusCode + '_ {
+    move |e| {
+        error!("FAIL-CLOSED: {}: {}", what, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+/// Known flag and a well-formed tenant; 400 otherwise.
+fn check_target(flag: &str, tenant_id: Option<&str>) -> Result<Flag, StatusCode> {
+    let Some(parsed) = Flag::parse(flag) else {
+        warn!("Rejected feature flag change: unknown flag '{}'", flag);
+        return Err(StatusCode::BAD_REQUEST);
+    };
+    if let Some(tenant) = tenant_id {
+        feature_flags::check_tenant(tenant).map_err(|e| {
+            warn!("Rejected feature flag change for {}: {}", flag, e);
+            StatusCode::BAD_REQUEST
+        })?;
+    }
+    Ok(parsed)
+}
+
+/// Apply a change to this instance now; others pick it up on their next refresh.
+async fn reload(state: &AppState, db: &Client) {
+    if let Err(e) = state.feature_flags.load_from_db(db).await {
+        warn!("Feature flag change stored but not reloaded (next refresh applies it): {}", e);
+    }
+}
+
+/// GET /admin/feature-flags (X-Admin-Key): every flag with its rows and effective value.
+#[utoipa::path(
+    get,
+    path = "/admin/feature-flags",
+    tag = "admin",
+    responses(
+        (status = 200, description = "All flags", body = FeatureFlagList),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_list_flags(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<FeatureFlagList>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    let db = control_db(&state)?;
+    let settings = feature_flags::read_settings(db).await.map_err(db_err("Failed to list feature flags"))?;
+    let flags = Flag::ALL
+        .into_iter()
+        .map(|flag| {
+            let (enabled, source) = state.feature_flags.resolve(flag, None);
+            let mine = settings.iter().filter(|s| s.flag == flag.as_str());
+            FeatureFlagStatus {
+                flag: flag.as_str().to_string(),
+                description: flag.description().to_string(),
+                default_enabled: flag.default_enabled(),
+                global: mine.clone().find(|s| s.tenant_id.is_none()).cloned(),
+                overrides: mine.filter(|s| s.tenant_id.is_some()).cloned().collect(),
+                enabled,
+                source,
+            }
+        })
+        .collect();
+    Ok(Json(FeatureFlagList { flags }))
+}
+
+/// POST /admin/feature-flags (X-Admin-Key): set a flag globally or for one tenant.
+#[utoipa::path(
+    post,
+    path = "/admin/feature-flags",
+    tag = "admin",
+    request_body = SetFeatureFlagRequest,
+    responses(
+        (status = 200, description = "Flag set and applied on this instance", body = SetFeatureFlagResponse),
+        (status = 400, description = "Unknown flag, invalid tenant_id, empty reason or changed_by"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_set_flag(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<SetFeatureFlagRequest>,
+) -> Result<Json<SetFeatureFlagResponse>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    let flag = check_target(&req.flag, req.tenant_id.as_deref())?;
+    if req.reason.trim().is_empty() || req.changed_by.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let db = control_db(&state)?;
+    // The CTE reads the row as it was before the upsert
+    let row = db
+        .query_one(
+            r#"
+            WITH previous AS (
+                SELECT enabled FROM feature_flags WHERE flag_key = $1 AND tenant_id IS NOT DISTINCT FROM $2
+            )
+            INSERT INTO feature_flags (flag_key, tenant_id, enabled, reason, updated_by)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (flag_key, (COALESCE(tenant_id, ''))) DO UPDATE
+            SET enabled = EXCLUDED.enabled, reason = EXCLUDED.reason, updated_by = EXCLUDED.updated_by, updated_at = now()
+            RETURNING (SELECT enabled FROM previous), updated_at
+            "#,
+            &[&flag.as_str(), &req.tenant_id, &req.enabled, &req.reason, &req.changed_by],
+        )
+        .await
+        .map_err(db_err("Failed to set feature flag"))?;
+    let previous: Option<bool> = row.get(0);
+    let updated_at: DateTime<Utc> = row.get(1);
+
+    http_agent_auth::audit(
+        state.store.as_ref(),
+        None,
+        "FEATURE_FLAG_CHANGED",
+        None,
+        &serde_json::json!({
+            "flag": flag.as_str(),
+            "tenant_id": req.tenant_id,
+            "enabled": req.enabled,
+            "previous": previous,
+            "changed_by": req.changed_by,
+            "reason": req.reason,
+        }),
+    )
+    .await?;
+    reload(&state, db).await;
+    info!(
+        "Feature flag set | flag={} | tenant={} | enabled={} | previous={:?} | by={}",
+        flag.as_str(),
+        req.tenant_id.as_deref().unwrap_or("*"),
+        req.enabled,
+        previous,
+        req.changed_by
+    );
+    Ok(Json(SetFeatureFlagResponse {
+        flag: flag.as_str().to_string(),
+        tenant_id: req.tenant_id,
+        enabled: req.enabled,
+        previous,
+        updated_at,
+    }))
+}
+
+/// POST /admin/feature-flags/clear (X-Admin-Key): remove a global value or tenant override; the
+/// next level (global, then default) applies again.
+#[utoipa::path(
+    post,
+    path = "/admin/feature-flags/clear",
+    tag = "admin",
+    request_body = ClearFeatureFlagRequest,
+    responses(
+        (status = 200, description = "Row removed and change applied on this instance", body = ClearFeatureFlagResponse),
+        (status = 400, description = "Unknown flag, invalid tenant_id, empty reason or cleared_by"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 404, description = "The flag is not set at this level"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_clear_flag(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<ClearFeatureFlagRequest>,
+) -> Result<Json<ClearFeatureFlagResponse>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    let flag = check_target(&req.flag, req.tenant_id.as_deref())?;
+    if req.reason.trim().is_empty() || req.cleared_by.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let db = control_db(&state)?;
+    let row = db
+        .query_opt(
+            "DELETE FROM feature_flags WHERE flag_key = $1 AND tenant_id IS NOT DISTINCT FROM $2 RETURNING enabled",
+            &[&flag.as_str(), &req.tenant_id],
+        )
+        .await
+        .map_err(db_err("Failed to clear feature flag"))?;
+    let Some(row) = row else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+    let previous: bool = row.get(0);
+
+    http_agent_auth::audit(
+        state.store.as_ref(),
+        None,
+        "FEATURE_FLAG_CLEARED",
+        None,
+        &serde_json::json!({
+            "flag": flag.as_str(),
+            "tenant_id": req.tenant_id,
+            "previous": previous,
+            "cleared_by": req.cleared_by,
+            "reason": req.reason,
+        }),
+    )
+    .await?;
+    reload(&state, db).await;
+    info!(
+        "Feature flag cleared | flag={} | tenant={} | previous={} | by={}",
+        flag.as_str(),
+        req.tenant_id.as_deref().unwrap_or("*"),
+        previous,
+        req.cleared_by
+    );
+    Ok(Json(ClearFeatureFlagResponse { flag: flag.as_str().to_string(), tenant_id: req.tenant_id, previous }))
+}
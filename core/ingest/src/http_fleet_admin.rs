@@ -0,0 +1,85 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/http_fleet_admin.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Authenticated admin endpoints for the fleet - latest host inventory per agent for vulnerability and coverage analysis
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use tracing::error;
+
+use crate::http_list::{ListQuery, ListQueryRejection, ListSpec, Page};
+use crate::http_server::AppState;
+
+const INVENTORY_WINDOW: i64 = 10_000;
+
+const INVENTORY_LIST: ListSpec = ListSpec {
+    filterable: &[
+        "agent_id",
+        "hostname",
+        "os_name",
+        "os_version",
+        "kernel_release",
+        "arch",
+        "disk_encryption",
+        "cpu_cores",
+        "memory_total_bytes",
+        "collected_at",
+    ],
+    selectable: &[
+        "agent_id",
+        "hostname",
+        "os_name",
+        "os_version",
+        "kernel_release",
+        "kernel_version",
+        "arch",
+        "cpu_model",
+        "cpu_cores",
+        "memory_total_bytes",
+        "security_tools",
+        "disk_encryption",
+        "encrypted_volumes",
+        "source_event_id",
+        "collected_at",
+        "received_at",
+    ],
+};
+
+/// GET /admin/fleet/inventory (X-Admin-Key): latest host inventory of every reporting agent, by
+/// hostname, as a list page. filter[kernel_release]=... or filter[disk_encryption][ne]=encrypted
+/// select exposed or uncovered hosts.
+#[utoipa::path(
+    get,
+    path = "/admin/fleet/inventory",
+    tag = "admin",
+    params(
+        ("limit" = Option<usize>, Query, description = "Page size (1-500, default 50)"),
+        ("cursor" = Option<String>, Query, description = "next_cursor of the previous page"),
+        ("fields" = Option<String>, Query, description = "Comma-separated sparse fieldset"),
+    ),
+    responses(
+        (status = 200, description = "Page of host inventories (HostInventoryRecord items)", body = Page),
+        (status = 400, description = "Unsupported list parameter"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "RANSOMEYE_ADMIN_KEY_PATH not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_list_inventory(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<ListQuery, ListQueryRejection>,
+) -> Result<Json<Page>, Response> {
+    state.admin_key.check(&headers).map_err(IntoResponse::into_response)?;
+    let query = query.map_err(IntoResponse::into_response)?;
+    let inventory = state.store.list_host_inventory(INVENTORY_WINDOW).await.map_err(|e| {
+        error!("Failed to read host_inventory: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR.into_response()
+    })?;
+    query
+        .paginate(&INVENTORY_LIST, inventory, |h| format!("{}|{}", h.hostname.as_deref().unwrap_or_default(), h.agent_id))
+        .map(Json)
+        .map_err(IntoResponse::into_response)
+}
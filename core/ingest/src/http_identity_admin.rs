@@ -0,0 +1,186 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/http_identity_admin.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Authenticated admin endpoints to list and resolve duplicate agent-id conflicts - resolution releases or discards the quarantined events and is audited
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use crypto::digest::Sha256;
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::http_list::{ListQuery, ListQueryRejection, ListSpec, Page};
+use crate::http_server::AppState;
+use crate::storage::{AuditRecord, ConflictResolution, IdentityConflictRecord};
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ResolveConflictRequest {
+    pub conflict_id: Uuid,
+    pub resolution: ConflictResolution,
+    /// Free-text operator justification recorded with the conflict and in the audit log.
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ResolveConflictResponse {
+    pub conflict_id: String,
+    pub resolution: ConflictResolution,
+    /// Quarantined events marked released or discarded
+    pub events: u64,
+}
+
+const CONFLICT_LIST: ListSpec = ListSpec {
+    filterable: &[
+        "component_type",
+        "component_identity",
+        "agent_id",
+        "opened_at",
+        "first_origin.source_ip",
+        "first_origin.host_id",
+        "conflicting_origin.source_ip",
+        "conflicting_origin.host_id",
+    ],
+    selectable: &[
+        "conflict_id",
+        "component_type",
+        "component_identity",
+        "agent_id",
+        "first_origin",
+        "conflicting_origin",
+        "opened_at",
+    ],
+};
+
+fn conflict_sort_key(c: &IdentityConflictRecord) -> String {
+    format!("{}|{}", c.opened_at.to_rfc3339_opts(SecondsFormat::Micros, true), c.conflict_id)
+}
+
+/// GET /admin/identity-conflicts (X-Admin-Key): open conflicts, oldest first, as a list page.
+#[utoipa::path(
+    get,
+    path = "/admin/identity-conflicts",
+    tag = "admin",
+    params(
+        ("limit" = Option<usize>, Query, description = "Page size (1-500, default 50)"),
+        ("cursor" = Option<String>, Query, description = "next_cursor of the previous page"),
+        ("fields" = Option<String>, Query, description = "Comma-separated sparse fieldset"),
+    ),
+    responses(
+        (status = 200, description = "Page of open conflicts (IdentityConflictRecord items)", body = Page),
+        (status = 400, description = "Unsupported list parameter"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "RANSOMEYE_ADMIN_KEY_PATH not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_list_conflicts(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<ListQuery, ListQueryRejection>,
+) -> Result<Json<Page>, Response> {
+    state.admin_key.check(&headers).map_err(IntoResponse::into_response)?;
+    let query = query.map_err(IntoResponse::into_response)?;
+    query
+        .paginate(&CONFLICT_LIST, state.identity_conflicts.open_conflicts(), conflict_sort_key)
+        .map(Json)
+        .map_err(IntoResponse::into_response)
+}
+
+/// POST /admin/identity-conflicts/resolve (X-Admin-Key): close a conflict and lift its quarantine.
+/// Resolve after fixing the duplicated identity; if both hosts still share it, the next events
+/// open a new conflict.
+#[utoipa::path(
+    post,
+    path = "/admin/identity-conflicts/resolve",
+    tag = "admin",
+    request_body = ResolveConflictRequest,
+    responses(
+        (status = 200, description = "Conflict resolved; quarantined events marked", body = ResolveConflictResponse),
+        (status = 400, description = "Empty reason"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 404, description = "No open conflict with this id"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_resolve_conflict(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<ResolveConflictRequest>,
+) -> Result<Json<ResolveConflictResponse>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    if req.reason.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let conflict = state.identity_conflicts.get(req.conflict_id).ok_or(StatusCode::NOT_FOUND)?;
+
+    let component_id = state.store.ingestion_component().await.map_err(|e| {
+        error!("FAIL-CLOSED: Failed to get/create ingestion component: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let mut tx = state.store.begin().await.map_err(|e| {
+        error!("Failed to start transaction: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let events = match tx.resolve_identity_conflict(req.conflict_id, req.resolution, &req.reason).await {
+        Ok(Some(events)) => events,
+        Ok(None) => {
+            tx.rollback().await;
+            // Resolved through another ingest instance; drop the stale in-memory state
+            warn!("Identity conflict {} is no longer open in storage", req.conflict_id);
+            state.identity_conflicts.resolve(req.conflict_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            error!("FAIL-CLOSED: Failed to resolve identity conflict: {}", e);
+            tx.rollback().await;
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let payload = serde_json::json!({
+        "conflict_id": req.conflict_id.to_string(),
+        "component_type": conflict.component_type,
+        "component_id": conflict.component_identity,
+        "resolution": req.resolution.as_str(),
+        "reason": req.reason,
+        "events": events,
+    });
+    let payload_str = serde_json::to_string(&payload).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let audit = AuditRecord {
+        actor_component_id: Some(component_id),
+        actor_agent_id: Some(conflict.agent_id),
+        action: "IDENTITY_CONFLICT_RESOLVED".to_string(),
+        object_type: "other".to_string(),
+        object_id: Some(req.conflict_id),
+        event_time: Some(Utc::now()),
+        payload_json: payload,
+        payload_sha256: Sha256::digest(payload_str.as_bytes()).to_vec(),
+    };
+    if let Err(e) = tx.append_audit(&audit).await {
+        error!("FAIL-CLOSED: Failed to insert IDENTITY_CONFLICT_RESOLVED audit log: {}", e);
+        tx.rollback().await;
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+    tx.commit().await.map_err(|e| {
+        error!("FAIL-CLOSED: Failed to commit identity conflict resolution: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    state.identity_conflicts.resolve(req.conflict_id);
+    // The operator may have re-keyed or re-enrolled the identity while it was in conflict
+    state.agent_cache.invalidate_identity(&conflict.component_identity);
+    info!(
+        "Identity conflict resolved | conflict_id={} | component_id={} | resolution={} | events={}",
+        req.conflict_id, conflict.component_identity, req.resolution.as_str(), events
+    );
+    Ok(Json(ResolveConflictResponse {
+        conflict_id: req.conflict_id.to_string(),
+        resolution: req.resolution,
+        events,
+    }))
+}
@@ -0,0 +1,276 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/http_legal_hold_admin.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Authenticated admin endpoints for legal holds - place, list and release holds on incidents and entities (all audited)
+
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use tokio_postgres::error::SqlState;
+use tokio_postgres::Client;
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::http_agent_auth;
+use crate::http_list::{ListQuery, ListQueryRejection, ListSpec, Page};
+use crate::http_server::AppState;
+use crate::legal_hold::{HoldSubject, LegalHold};
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct PlaceHoldRequest {
+    pub subject_type: HoldSubject,
+    pub subject_id: Uuid,
+    /// Matter or case reference justifying the hold
+    pub reason: String,
+    pub placed_by: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PlaceHoldResponse {
+    pub hold_id: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ReleaseHoldRequest {
+    pub hold_id: Uuid,
+    pub released_by: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReleaseHoldResponse {
+    /// Rows changed (always 1; an unknown or already released hold is a 404)
+    pub updated: u64,
+}
+
+const HOLD_LIST: ListSpec = ListSpec {
+    filterable: &["subject_type", "subject_id", "placed_by", "placed_at", "active"],
+    selectable: &[
+        "hold_id",
+        "subject_type",
+        "subject_id",
+        "reason",
+        "placed_by",
+        "placed_at",
+        "released_by",
+        "release_reason",
+        "released_at",
+        "active",
+    ],
+};
+
+fn control_db(state: &AppState) -> Result<&Arc<Client>, StatusCode> {
+    state.db.as_ref().ok_or_else(|| {
+        error!("Legal hold operation requested but storage backend has no control plane (postgres required)");
+        StatusCode::SERVICE_UNAVAILABLE
+    })
+}
+
+fn db_err(what: &str) -> impl FnOnce(tokio_postgres::Error) -> StatusCode + '_ {
+    move |e| {
+        error!("FAIL-CLOSED: {}: {}", what, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+fn micros_key(at: DateTime<Utc>, id: Uuid) -> String {
+    format!("{}|{}", at.to_rfc3339_opts(SecondsFormat::Micros, true), id)
+}
+
+/// POST /admin/legal-holds (X-Admin-Key): exempt an incident or entity from retention.
+#[utoipa::path(
+    post,
+    path = "/admin/legal-holds",
+    tag = "admin",
+    request_body = PlaceHoldRequest,
+    responses(
+        (status = 200, description = "Hold placed", body = PlaceHoldResponse),
+        (status = 400, description = "Empty reason or placed_by"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 409, description = "The subject already has an active hold"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_place_hold(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<PlaceHoldRequest>,
+) -> Result<Json<PlaceHoldResponse>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    if req.reason.trim().is_empty() || req.placed_by.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let db = control_db(&state)?;
+    let hold_id = Uuid::new_v4();
+    let inserted = db
+        .execute(
+            r#"
+            INSERT INTO legal_holds (hold_id, subject_type, subject_id, reason, placed_by)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            &[&hold_id, &req.subject_type.as_str(), &req.subject_id, &req.reason, &req.placed_by],
+        )
+        .await;
+    if let Err(e) = inserted {
+        if e.code() == Some(&SqlState::UNIQUE_VIOLATION) {
+            warn!(
+                "Rejected legal hold: {} {} is already held",
+                req.subject_type.as_str(),
+                req.subject_id
+            );
+            return Err(StatusCode::CONFLICT);
+        }
+        return Err(db_err("Failed to insert legal hold")(e));
+    }
+
+    http_agent_auth::audit(
+        state.store.as_ref(),
+        None,
+        "LEGAL_HOLD_PLACED",
+        Some(hold_id),
+        &serde_json::json!({
+            "hold_id": hold_id.to_string(),
+            "subject_type": req.subject_type.as_str(),
+            "subject_id": req.subject_id.to_string(),
+            "reason": req.reason,
+            "placed_by": req.placed_by,
+        }),
+    )
+    .await?;
+    info!(
+        "Legal hold placed | hold_id={} | {}={} | by={}",
+        hold_id,
+        req.subject_type.as_str(),
+        req.subject_id,
+        req.placed_by
+    );
+    Ok(Json(PlaceHoldResponse { hold_id: hold_id.to_string() }))
+}
+
+/// GET /admin/legal-holds (X-Admin-Key): holds, oldest first, as a list page.
+/// filter[active]=true lists the holds retention currently honours.
+#[utoipa::path(
+    get,
+    path = "/admin/legal-holds",
+    tag = "admin",
+    params(
+        ("limit" = Option<usize>, Query, description = "Page size (1-500, default 50)"),
+        ("cursor" = Option<String>, Query, description = "next_cursor of the previous page"),
+        ("fields" = Option<String>, Query, description = "Comma-separated sparse fieldset"),
+    ),
+    responses(
+        (status = 200, description = "Page of holds (LegalHold items)", body = Page),
+        (status = 400, description = "Unsupported list parameter"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_list_holds(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<ListQuery, ListQueryRejection>,
+) -> Result<Json<Page>, Response> {
+    state.admin_key.check(&headers).map_err(IntoResponse::into_response)?;
+    let query = query.map_err(IntoResponse::into_response)?;
+    let db = control_db(&state).map_err(IntoResponse::into_response)?;
+    let rows = db
+        .query(
+            r#"
+            SELECT hold_id, subject_type, subject_id, reason, placed_by, placed_at,
+                   released_by, release_reason, released_at
+            FROM legal_holds
+            "#,
+            &[],
+        )
+        .await
+        .map_err(db_err("Failed to list legal holds"))
+        .map_err(IntoResponse::into_response)?;
+    let mut holds = Vec::with_capacity(rows.len());
+    for r in &rows {
+        let subject_type: String = r.get(1);
+        let subject_type = HoldSubject::parse(&subject_type).ok_or_else(|| {
+            error!("FAIL-CLOSED: legal_holds has unknown subject_type '{}'", subject_type);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        })?;
+        let released_at: Option<DateTime<Utc>> = r.get(8);
+        holds.push(LegalHold {
+            hold_id: r.get(0),
+            subject_type,
+            subject_id: r.get(2),
+            reason: r.get(3),
+            placed_by: r.get(4),
+            placed_at: r.get(5),
+            released_by: r.get(6),
+            release_reason: r.get(7),
+            released_at,
+            active: released_at.is_none(),
+        });
+    }
+    query
+        .paginate(&HOLD_LIST, holds, |h| micros_key(h.placed_at, h.hold_id))
+        .map(Json)
+        .map_err(IntoResponse::into_response)
+}
+
+/// POST /admin/legal-holds/release (X-Admin-Key): end a hold; the next retention run purges
+/// whatever is past its policy.
+#[utoipa::path(
+    post,
+    path = "/admin/legal-holds/release",
+    tag = "admin",
+    request_body = ReleaseHoldRequest,
+    responses(
+        (status = 200, description = "Hold released", body = ReleaseHoldResponse),
+        (status = 400, description = "Empty reason or released_by"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 404, description = "No active hold with this id"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_release_hold(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<ReleaseHoldRequest>,
+) -> Result<Json<ReleaseHoldResponse>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    if req.reason.trim().is_empty() || req.released_by.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let updated = control_db(&state)?
+        .execute(
+            r#"
+            UPDATE legal_holds
+            SET released_at = now(), released_by = $2, release_reason = $3
+            WHERE hold_id = $1 AND released_at IS NULL
+            "#,
+            &[&req.hold_id, &req.released_by, &req.reason],
+        )
+        .await
+        .map_err(db_err("Failed to release legal hold"))?;
+    if updated == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    http_agent_auth::audit(
+        state.store.as_ref(),
+        None,
+        "LEGAL_HOLD_RELEASED",
+        Some(req.hold_id),
+        &serde_json::json!({
+            "hold_id": req.hold_id.to_string(),
+            "released_by": req.released_by,
+            "reason": req.reason,
+        }),
+    )
+    .await?;
+    info!("Legal hold released | hold_id={} | by={}", req.hold_id, req.released_by);
+    Ok(Json(ReleaseHoldResponse { updated }))
+}
@@ -0,0 +1,291 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/http_list.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Shared list-endpoint conventions - ListQuery extractor (cursor pagination, filter[...] params, sparse fieldsets) and the Page envelope (data, next_cursor, total_estimate)
+
+/*
+ * List API Conventions
+ *
+ * Every list endpoint takes the same query parameters:
+ *
+ *   limit=<n>                       page size, 1..=MAX_LIMIT (default DEFAULT_LIMIT)
+ *   cursor=<opaque>                 next_cursor of the previous page
+ *   filter[<field>]=<value>         equality
+ *   filter[<field>][<op>]=<value>   op = eq|ne|gt|gte|lt|lte|in (in: comma-separated values)
+ *   fields=<a>,<b>                  sparse fieldset: only these top-level fields per item
+ *
+ * Keys and values are percent-decoded (RFC 3986). Nested fields are addressed with dots
+ * (filter[first_origin.source_ip]=10.0.0.1). Each endpoint declares a ListSpec naming its
+ * filterable and selectable fields; anything else is a 400 rather than being silently ignored.
+ *
+ * Responses use one envelope: {"data": [...], "next_cursor": "..." | null, "total_estimate": n}.
+ * Items are ordered by an endpoint-defined sort key; the cursor encodes the last key served, so
+ * pages stay stable while rows are added. total_estimate is the number of items matching the
+ * filters (exact for in-memory lists, a planner estimate where an endpoint counts in SQL).
+ */
+
+use std::cmp::Ordering;
+
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{request::Parts, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use serde::Serialize;
+use serde_json::{Map, Value as JsonValue};
+use utoipa::ToSchema;
+
+pub const DEFAULT_LIMIT: usize = 50;
+pub const MAX_LIMIT: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterOp {
+    Eq,
+    Ne,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    In,
+}
+
+impl FilterOp {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "eq" => Some(FilterOp::Eq),
+            "ne" => Some(FilterOp::Ne),
+            "gt" => Some(FilterOp::Gt),
+            "gte" => Some(FilterOp::Gte),
+            "lt" => Some(FilterOp::Lt),
+            "lte" => Some(FilterOp::Lte),
+            "in" => Some(FilterOp::In),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    /// Dotted field path
+    pub field: String,
+    pub op: FilterOp,
+    pub value: String,
+}
+
+/// Parsed list query parameters (see module docs).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListQuery {
+    pub limit: usize,
+    pub cursor: Option<String>,
+    pub filters: Vec<Filter>,
+    pub fields: Option<Vec<String>>,
+}
+
+impl Default for ListQuery {
+    fn default() -> Self {
+        Self {
+            limit: DEFAULT_LIMIT,
+            cursor: None,
+            filters: Vec::new(),
+            fields: None,
+        }
+    }
+}
+
+/// 400 with a reason, for malformed or unsupported list parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListQueryRejection(pub String);
+
+impl IntoResponse for ListQueryRejection {
+    fn into_response(self) -> Response {
+        (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": self.0 }))).into_response()
+    }
+}
+
+/// Fields an endpoint allows in filter[...] and fields=.
+#[derive(Debug, Clone, Copy)]
+pub struct ListSpec {
+    pub filterable: &'static [&'static str],
+    pub selectable: &'static [&'static str],
+}
+
+/// Response envelope shared by all list endpoints.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct Page {
+    #[schema(value_type = Vec<Object>)]
+    pub data: Vec<JsonValue>,
+    pub next_cursor: Option<String>,
+    pub total_estimate: u64,
+}
+
+impl ListQuery {
+    /// Parse a raw (still percent-encoded) query string.
+    pub fn parse(raw: &str) -> Result<Self, ListQueryRejection> {
+        let reject = |msg: String| Err(ListQueryRejection(msg));
+        let mut query = ListQuery::default();
+        for (key, value) in url::form_urlencoded::parse(raw.as_bytes()) {
+            match key.as_ref() {
+                "limit" => match value.parse::<usize>() {
+                    Ok(n) if (1..=MAX_LIMIT).contains(&n) => query.limit = n,
+                    _ => return reject(format!("limit must be between 1 and {}", MAX_LIMIT)),
+                },
+                "cursor" => query.cursor = Some(value.into_owned()),
+                "fields" => {
+                    let fields: Vec<String> = value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|f| !f.is_empty())
+                        .map(str::to_string)
+                        .collect();
+                    if fields.is_empty() {
+                        return reject("fields must name at least one field".to_string());
+                    }
+                    query.fields = Some(fields);
+                }
+                k if k.starts_with("filter[") => {
+                    let Some((field, op)) = parse_filter_key(k) else {
+                        return reject(format!("malformed filter parameter '{}'", k));
+                    };
+                    query.filters.push(Filter { field, op, value: value.into_owned() });
+                }
+                other => return reject(format!("unknown query parameter '{}'", other)),
+            }
+        }
+        Ok(query)
+    }
+
+    /// Reject filters and fields the endpoint does not support.
+    pub fn validate(&self, spec: &ListSpec) -> Result<(), ListQueryRejection> {
+        if let Some(f) = self.filters.iter().find(|f| !spec.filterable.contains(&f.field.as_str())) {
+            return Err(ListQueryRejection(format!("field '{}' is not filterable", f.field)));
+        }
+        if let Some(fields) = &self.fields {
+            if let Some(f) = fields.iter().find(|f| !spec.selectable.contains(&f.as_str())) {
+                return Err(ListQueryRejection(format!("field '{}' is not selectable", f)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Filter, page and project an in-memory list. `sort_key` must be unique per item; items
+    /// are served in ascending key order.
+    pub fn paginate<T, K>(&self, spec: &ListSpec, items: Vec<T>, sort_key: K) -> Result<Page, ListQueryRejection>
+    where
+        T: Serialize,
+        K: Fn(&T) -> String,
+    {
+        self.validate(spec)?;
+        let after = match &self.cursor {
+            Some(c) => Some(decode_cursor(c)?),
+            None => None,
+        };
+
+        let mut rows = Vec::with_capacity(items.len());
+        for item in &items {
+            let value = serde_json::to_value(item)
+                .map_err(|e| ListQueryRejection(format!("item serialization failed: {}", e)))?;
+            if self.filters.iter().all(|f| filter_matches(f, &value)) {
+                rows.push((sort_key(item), value));
+            }
+        }
+        rows.sort_by(|a, b| a.0.cmp(&b.0));
+        let total_estimate = rows.len() as u64;
+
+        let start = match &after {
+            Some(key) => rows.partition_point(|(k, _)| k.as_str() <= key.as_str()),
+            None => 0,
+        };
+        let end = (start + self.limit).min(rows.len());
+        let next_cursor = if end < rows.len() {
+            Some(encode_cursor(&rows[end - 1].0))
+        } else {
+            None
+        };
+        let data = rows
+            .drain(start..end)
+            .map(|(_, value)| self.project(value))
+            .collect();
+        Ok(Page { data, next_cursor, total_estimate })
+    }
+
+    /// Apply the sparse fieldset to one serialized item.
+    pub fn project(&self, value: JsonValue) -> JsonValue {
+        match (&self.fields, value) {
+            (Some(fields), JsonValue::Object(mut obj)) => {
+                let mut out = Map::new();
+                for f in fields {
+                    if let Some(v) = obj.remove(f) {
+                        out.insert(f.clone(), v);
+                    }
+                }
+                JsonValue::Object(out)
+            }
+            (_, value) => value,
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ListQuery {
+    type Rejection = ListQueryRejection;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        ListQuery::parse(parts.uri.query().unwrap_or(""))
+    }
+}
+
+/// "filter[a.b]" / "filter[a.b][op]" -> (field, op)
+fn parse_filter_key(key: &str) -> Option<(String, FilterOp)> {
+    let rest = key.strip_prefix("filter[")?;
+    let (field, rest) = rest.split_once(']')?;
+    if field.is_empty() {
+        return None;
+    }
+    let op = match rest {
+        "" => FilterOp::Eq,
+        _ => FilterOp::parse(rest.strip_prefix('[')?.strip_suffix(']')?)?,
+    };
+    Some((field.to_string(), op))
+}
+
+pub fn encode_cursor(sort_key: &str) -> String {
+    URL_SAFE_NO_PAD.encode(sort_key.as_bytes())
+}
+
+pub fn decode_cursor(cursor: &str) -> Result<String, ListQueryRejection> {
+    URL_SAFE_NO_PAD
+        .decode(cursor.as_bytes())
+        .ok()
+        .and_then(|b| String::from_utf8(b).ok())
+        .ok_or_else(|| ListQueryRejection("invalid cursor".to_string()))
+}
+
+fn lookup<'a>(value: &'a JsonValue, path: &str) -> Option<&'a JsonValue> {
+    path.split('.').try_fold(value, |v, part| v.get(part))
+}
+
+/// Compare a JSON field with a query-string value: numbers numerically, everything else as text.
+fn compare(field: &JsonValue, value: &str) -> Option<Ordering> {
+    match field {
+        JsonValue::Number(n) => n.as_f64()?.partial_cmp(&value.parse::<f64>().ok()?),
+        JsonValue::String(s) => Some(s.as_str().cmp(value)),
+        JsonValue::Bool(b) => Some(b.cmp(&value.parse::<bool>().ok()?)),
+        JsonValue::Null => (value == "null").then_some(Ordering::Equal),
+        _ => None,
+    }
+}
+
+fn filter_matches(filter: &Filter, item: &JsonValue) -> bool {
+    let field = lookup(item, &filter.field).unwrap_or(&JsonValue::Null);
+    let ord = |v: &str| compare(field, v);
+    match filter.op {
+        FilterOp::Eq => ord(&filter.value) == Some(Ordering::Equal),
+        FilterOp::Ne => ord(&filter.value) != Some(Ordering::Equal),
+        FilterOp::Gt => ord(&filter.value) == Some(Ordering::Greater),
+        FilterOp::Gte => matches!(ord(&filter.value), Some(Ordering::Greater | Ordering::Equal)),
+        FilterOp::Lt => ord(&filter.value) == Some(Ordering::Less),
+        FilterOp::Lte => matches!(ord(&filter.value), Some(Ordering::Less | Ordering::Equal)),
+        FilterOp::In => filter.value.split(',').any(|v| ord(v.trim()) == Some(Ordering::Equal)),
+    }
+}
@@ -3,40 +3,93 @@
 // Details of functionality of this file: HTTP ingestion server main entry point - listens on :8080 and accepts Linux Agent + DPI Probe telemetry
 
 use std::env;
+use std::sync::Arc;
 use tokio::signal;
-use tracing::{info, error};
+use tokio::signal::unix::{signal as unix_signal, SignalKind};
+use tracing::{info, error, warn};
 
-mod http_server;
+use ingest::runtime_controls::{RuntimeChange, RuntimeControlConfig, RuntimeControls};
+use ingest::{http_runtime_admin, http_server};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    tracing_subscriber::fmt::init();
+    ingest::BUILD_INFO.handle_version_flag("ingest-http");
+    kernel::crash::install("ransomeye-ingestion", Some(ingest::crash_report::reporter()));
+    let otel_guard = kernel::otel::init_tracing("ransomeye-ingestion");
+    let controls = Arc::new(RuntimeControls::new(
+        Some(otel_guard.log_filter()),
+        otel_guard.baseline_filter(),
+        RuntimeControlConfig::from_env()?,
+    )?);
 
     info!("Starting RansomEye HTTP Ingestion Server");
 
+    // FAIL-CLOSED: a release deployment only runs the exact build its release manifest lists
+    let build = &ingest::BUILD_INFO;
+    match build.verify_release_from_env("ingest-http")? {
+        Some(release) => info!("Build verified against release {} | commit={}", release, build.git_commit),
+        None => warn!("RANSOMEYE_RELEASE_MANIFEST not set; build commit={} not verified against a release", build.git_commit),
+    }
+
+    // FAIL-CLOSED: the compiled-in crypto backend must pass its self-check
+    crypto::self_check()?;
+    info!("Crypto backend: {}", crypto::backend());
+
     // Get listen address from environment (default: 127.0.0.1:8080)
     let listen_addr = env::var("RANSOMEYE_INGESTION_LISTEN_ADDR")
         .unwrap_or_else(|_| "127.0.0.1:8080".to_string());
 
     // Create and start server
-    let server = http_server::HttpIngestionServer::new(listen_addr.clone()).await?;
+    let server = http_server::HttpIngestionServer::new(listen_addr.clone(), controls.clone()).await?;
+    let state = server.app_state();
     
     info!("HTTP Ingestion Server initialized, starting on {}", listen_addr);
 
-    // Start server in background
-    let server_handle = tokio::spawn(async move {
+    // Start server in background; it returns once a drain (signal or handoff) has completed
+    let drain = server.drain();
+    let mut server_handle = tokio::spawn(async move {
         if let Err(e) = server.start().await {
             error!("Server error: {}", e);
             std::process::exit(1);
         }
     });
 
-    // Wait for shutdown signal
-    signal::ctrl_c().await?;
-    info!("Shutdown signal received");
+    // Wait for shutdown signal; SIGUSR1 applies RANSOMEYE_SIGUSR1_LOG_FILTER for the default TTL
+    let mut sigusr1 = unix_signal(SignalKind::user_defined1())?;
+    let mut sigterm = unix_signal(SignalKind::terminate())?;
+    loop {
+        tokio::select! {
+            res = signal::ctrl_c() => {
+                res?;
+                info!("Shutdown signal received");
+                drain.begin("ctrl_c");
+                break;
+            }
+            _ = sigterm.recv() => {
+                info!("SIGTERM received");
+                drain.begin("sigterm");
+                break;
+            }
+            // Handoff: a successor took over and in-flight requests have finished
+            _ = &mut server_handle => {
+                info!("HTTP Ingestion Server stopped");
+                return Ok(());
+            }
+            _ = sigusr1.recv() => {
+                let change = RuntimeChange {
+                    log_filter: Some(controls.config().signal_log_filter.clone()),
+                    sample_ratio: None,
+                    ttl_secs: None,
+                };
+                if let Err(code) = http_runtime_admin::apply_override(&state.store, &controls, &change, "sigusr1", None).await {
+                    error!("SIGUSR1 runtime config override failed ({})", code);
+                }
+            }
+        }
+    }
 
-    // Cancel server task
-    server_handle.abort();
+    // In-flight requests finish (bounded by RANSOMEYE_INGEST_DRAIN_TIMEOUT_SECS)
+    let _ = server_handle.await;
     
     info!("HTTP Ingestion Server stopped");
     Ok(())
@@ -0,0 +1,281 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/http_maintenance_admin.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Authenticated admin endpoints for maintenance windows - open scoped windows, list them with their hit counts, end them early and report what they suppressed and downgraded (changes audited)
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use tokio_postgres::types::Json as PgJson;
+use tokio_postgres::Client;
+use tracing::{error, info, warn};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::http_agent_auth;
+use crate::http_list::{ListQuery, ListQueryRejection, ListSpec, Page};
+use crate::http_server::AppState;
+use crate::maintenance::{self, MaintenanceReport, MaintenanceWindowSpec};
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreateMaintenanceWindowResponse {
+    pub window_id: String,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CancelMaintenanceWindowRequest {
+    pub window_id: Uuid,
+    pub cancelled_by: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CancelMaintenanceWindowResponse {
+    /// Rows changed (always 1; an unknown or ended window is a 404)
+    pub updated: u64,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct MaintenanceReportQuery {
+    pub window_id: Uuid,
+}
+
+const WINDOW_LIST: ListSpec = ListSpec {
+    filterable: &["action", "created_by", "starts_at", "ends_at", "status", "suppressed_count", "downgraded_count"],
+    selectable: &[
+        "window_id",
+        "scope",
+        "starts_at",
+        "ends_at",
+        "action",
+        "downgrade_to",
+        "classes",
+        "reason",
+        "created_by",
+        "created_at",
+        "cancelled_at",
+        "cancelled_by",
+        "cancel_reason",
+        "suppressed_count",
+        "downgraded_count",
+        "last_hit_at",
+        "status",
+    ],
+};
+
+fn control_db(state: &AppState) -> Result<&Arc<Client>, StatusCode> {
+    state.db.as_ref().ok_or_else(|| {
+        error!("Maintenance window operation requested but storage backend has no control plane (postgres required)");
+        StatusCode::SERVICE_UNAVAILABLE
+    })
+}
+
+fn db_err(what: &str) -> impl FnOnce(tokio_postgres::Error) -> StatusCode + '_ {
+    move |e| {
+        error!("FAIL-CLOSED: {}: {}", what, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+fn read_err(e: String) -> StatusCode {
+    error!("FAIL-CLOSED: {}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+}
+
+/// POST /admin/maintenance-windows (X-Admin-Key): open a window for a host, zone or tenant.
+#[utoipa::path(
+    post,
+    path = "/admin/maintenance-windows",
+    tag = "admin",
+    request_body = MaintenanceWindowSpec,
+    responses(
+        (status = 200, description = "Window scheduled or active", body = CreateMaintenanceWindowResponse),
+        (status = 400, description = "Invalid window (scope, range, action, classes, reason)"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_create_window(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(spec): Json<MaintenanceWindowSpec>,
+) -> Result<Json<CreateMaintenanceWindowResponse>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    let db = control_db(&state)?;
+    spec.validate(Utc::now()).map_err(|e| {
+        warn!("Rejected maintenance window: {}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+
+    let window_id = Uuid::new_v4();
+    db.execute(
+        r#"
+        INSERT INTO maintenance_windows (
+            window_id, scope_kind, scope_value, starts_at, ends_at, action, downgrade_to, classes, reason, created_by
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7::text::severity_level, $8, $9, $10)
+        "#,
+        &[
+            &window_id,
+            &spec.scope.kind.as_str(),
+            &spec.scope.value,
+            &spec.starts_at,
+            &spec.ends_at,
+            &spec.action.as_str(),
+            &spec.downgrade_to,
+            &PgJson(&spec.classes),
+            &spec.reason,
+            &spec.created_by,
+        ],
+    )
+    .await
+    .map_err(db_err("Failed to insert maintenance window"))?;
+
+    let mut payload = serde_json::to_value(&spec).map_err(|e| read_err(e.to_string()))?;
+    payload["window_id"] = serde_json::json!(window_id.to_string());
+    http_agent_auth::audit(state.store.as_ref(), None, "MAINTENANCE_WINDOW_CREATED", Some(window_id), &payload).await?;
+    info!(
+        "Maintenance window opened | window_id={} | scope={}:{} | action={} | {} - {} | by={}",
+        window_id,
+        spec.scope.kind.as_str(),
+        spec.scope.value,
+        spec.action.as_str(),
+        spec.starts_at.to_rfc3339(),
+        spec.ends_at.to_rfc3339(),
+        spec.created_by
+    );
+    Ok(Json(CreateMaintenanceWindowResponse { window_id: window_id.to_string(), starts_at: spec.starts_at, ends_at: spec.ends_at }))
+}
+
+/// GET /admin/maintenance-windows (X-Admin-Key): windows with their hit counts, newest start
+/// first, as a list page. filter[status]=active lists the windows in force.
+#[utoipa::path(
+    get,
+    path = "/admin/maintenance-windows",
+    tag = "admin",
+    params(
+        ("limit" = Option<usize>, Query, description = "Page size (1-500, default 50)"),
+        ("cursor" = Option<String>, Query, description = "next_cursor of the previous page"),
+        ("fields" = Option<String>, Query, description = "Comma-separated sparse fieldset"),
+    ),
+    responses(
+        (status = 200, description = "Page of windows (MaintenanceWindow items)", body = Page),
+        (status = 400, description = "Unsupported list parameter"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_list_windows(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<ListQuery, ListQueryRejection>,
+) -> Result<Json<Page>, Response> {
+    state.admin_key.check(&headers).map_err(IntoResponse::into_response)?;
+    let query = query.map_err(IntoResponse::into_response)?;
+    let db = control_db(&state).map_err(IntoResponse::into_response)?;
+    let windows = maintenance::list(db, Utc::now())
+        .await
+        .map_err(read_err)
+        .map_err(IntoResponse::into_response)?;
+    // Newest start first: invert the timestamp so ascending key order is descending time
+    query
+        .paginate(&WINDOW_LIST, windows, |w| {
+            format!("{:020}|{}", i64::MAX - w.spec.starts_at.timestamp_micros(), w.window_id)
+        })
+        .map(Json)
+        .map_err(IntoResponse::into_response)
+}
+
+/// POST /admin/maintenance-windows/cancel (X-Admin-Key): end a scheduled or active window now;
+/// the row stays as history with its hit counts.
+#[utoipa::path(
+    post,
+    path = "/admin/maintenance-windows/cancel",
+    tag = "admin",
+    request_body = CancelMaintenanceWindowRequest,
+    responses(
+        (status = 200, description = "Window ended", body = CancelMaintenanceWindowResponse),
+        (status = 400, description = "Empty reason or cancelled_by"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 404, description = "No scheduled or active window with this id"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_cancel_window(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<CancelMaintenanceWindowRequest>,
+) -> Result<Json<CancelMaintenanceWindowResponse>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    if req.reason.trim().is_empty() || req.cancelled_by.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let updated = control_db(&state)?
+        .execute(
+            r#"
+            UPDATE maintenance_windows
+            SET cancelled_at = now(), cancelled_by = $2, cancel_reason = $3
+            WHERE window_id = $1 AND cancelled_at IS NULL AND ends_at > now()
+            "#,
+            &[&req.window_id, &req.cancelled_by, &req.reason],
+        )
+        .await
+        .map_err(db_err("Failed to cancel maintenance window"))?;
+    if updated == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    http_agent_auth::audit(
+        state.store.as_ref(),
+        None,
+        "MAINTENANCE_WINDOW_CANCELLED",
+        Some(req.window_id),
+        &serde_json::json!({
+            "window_id": req.window_id.to_string(),
+            "cancelled_by": req.cancelled_by,
+            "reason": req.reason,
+            "cancelled_at": Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        }),
+    )
+    .await?;
+    info!("Maintenance window cancelled | window_id={} | by={}", req.window_id, req.cancelled_by);
+    Ok(Json(CancelMaintenanceWindowResponse { updated }))
+}
+
+/// GET /admin/maintenance-windows/report?window_id= (X-Admin-Key): what a window suppressed and
+/// downgraded, by detection class and per detection.
+#[utoipa::path(
+    get,
+    path = "/admin/maintenance-windows/report",
+    tag = "admin",
+    params(MaintenanceReportQuery),
+    responses(
+        (status = 200, description = "Detections held back by the window", body = MaintenanceReport),
+        (status = 400, description = "Missing or malformed window_id"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 404, description = "Unknown window"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_window_report(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<MaintenanceReportQuery>,
+) -> Result<Json<MaintenanceReport>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    maintenance::report(control_db(&state)?, query.window_id, Utc::now())
+        .await
+        .map_err(read_err)?
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
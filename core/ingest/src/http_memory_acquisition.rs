@@ -0,0 +1,871 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/http_memory_acquisition.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Memory acquisition endpoints - operators request (policy-gated, two-person) and list acquisitions; Linux agents claim their own requests, stream the image in hashed chunks and complete or fail it (agent bearer token, audited)
+
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+    Extension,
+};
+use axum::body::Bytes;
+use chrono::{DateTime, SecondsFormat, Utc};
+use crypto::digest::Sha256;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::error::SqlState;
+use tokio_postgres::Client;
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::disk_quota;
+use crate::feature_flags::Flag;
+use crate::http_agent_auth::{self, AuthenticatedAgent};
+use crate::http_list::{ListQuery, ListQueryRejection, ListSpec, Page};
+use crate::http_server::AppState;
+use crate::memory_acquisition::{
+    self, AcquisitionOrder, AcquisitionRequest, AcquisitionScope, ChunkEntry, CustodyEntry, MemoryAcquisition,
+    MemoryAcquisitionPolicy, SpoolManifest, MAX_TEXT_BYTES,
+};
+
+/// Chunk header naming the acquisition
+pub const ACQUISITION_ID_HEADER: &str = "x-acquisition-id";
+/// Chunk header with the zero-based position of the chunk in the image
+pub const CHUNK_INDEX_HEADER: &str = "x-chunk-index";
+/// Chunk header with the hex SHA-256 of the body
+pub const CONTENT_SHA256_HEADER: &str = "x-content-sha256";
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AcquisitionRequestResponse {
+    pub acquisition_id: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ChunkUploadResponse {
+    pub acquisition_id: String,
+    pub chunk_index: u32,
+    pub chunk_sha256: String,
+    /// Chunks recorded so far
+    pub chunks_received: i32,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AcquisitionCompleteRequest {
+    pub acquisition_id: Uuid,
+    /// Number of chunks the agent sent
+    pub chunk_count: u32,
+    /// Size of the whole image
+    pub image_bytes: u64,
+    /// Hex SHA-256 of the whole image
+    pub image_sha256: String,
+    /// Acquisition tool as run by the agent (program path)
+    pub tool: String,
+    pub tool_version: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AcquisitionCompleteResponse {
+    pub acquisition_id: String,
+    pub image_sha256: String,
+    pub image_bytes: u64,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AcquisitionFailRequest {
+    pub acquisition_id: Uuid,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AcquisitionFailResponse {
+    /// Rows changed (always 1; an unknown or finished acquisition is a 404)
+    pub updated: u64,
+}
+
+const ACQUISITION_LIST: ListSpec = ListSpec {
+    filterable: &["agent_id", "incident_id", "scope", "status", "requested_by", "approved_by", "requested_at"],
+    selectable: &[
+        "acquisition_id",
+        "agent_id",
+        "incident_id",
+        "scope",
+        "pid",
+        "reason",
+        "requested_by",
+        "approved_by",
+        "status",
+        "requested_at",
+        "claimed_at",
+        "last_activity_at",
+        "finished_at",
+        "chunks_received",
+        "bytes_received",
+        "image_sha256",
+        "tool",
+        "failure_reason",
+    ],
+};
+
+fn control_db(state: &AppState) -> Result<&Arc<Client>, StatusCode> {
+    state.db.as_ref().ok_or_else(|| {
+        error!("Memory acquisition operation requested but storage backend has no control plane (postgres required)");
+        StatusCode::SERVICE_UNAVAILABLE
+    })
+}
+
+fn acquisition_policy(state: &AppState) -> Result<&MemoryAcquisitionPolicy, StatusCode> {
+    state.memory_acquisition.as_deref().ok_or_else(|| {
+        warn!("Memory acquisition operation requested but acquisition is off (RANSOMEYE_INGEST_MEMORY_ACQUISITION)");
+        StatusCode::SERVICE_UNAVAILABLE
+    })
+}
+
+fn db_err(what: &str) -> impl FnOnce(tokio_postgres::Error) -> StatusCode + '_ {
+    move |e| {
+        error!("FAIL-CLOSED: {}: {}", what, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+/// The control channel is for token-authenticated Linux agents only.
+fn linux_agent(auth: Option<Extension<AuthenticatedAgent>>) -> Result<AuthenticatedAgent, StatusCode> {
+    let Some(Extension(auth)) = auth else {
+        warn!("AUTH REJECT: memory acquisition control channel requires an agent token");
+        return Err(StatusCode::UNAUTHORIZED);
+    };
+    if auth.agent_type != "linux_agent" {
+        warn!("AUTH REJECT: agent {} ({}) is not a Linux agent", auth.agent_id, auth.agent_type);
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(auth)
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Result<&'a str, StatusCode> {
+    headers.get(name).and_then(|v| v.to_str().ok()).map(str::trim).ok_or_else(|| {
+        warn!("Rejected memory chunk: missing or invalid {} header", name);
+        StatusCode::BAD_REQUEST
+    })
+}
+
+fn is_sha256_hex(s: &str) -> bool {
+    s.len() == 64 && s.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+fn micros_key(at: DateTime<Utc>, id: Uuid) -> String {
+    format!("{}|{}", at.to_rfc3339_opts(SecondsFormat::Micros, true), id)
+}
+
+/// POST /admin/memory-acquisitions (X-Admin-Key): ask a Linux agent for a memory image. The
+/// request must pass the ingest policy (scope allowed, bounds) and name a second approver.
+#[utoipa::path(
+    post,
+    path = "/admin/memory-acquisitions",
+    tag = "admin",
+    request_body = AcquisitionRequest,
+    responses(
+        (status = 200, description = "Acquisition requested", body = AcquisitionRequestResponse),
+        (status = 400, description = "Refused by policy (scope, pid, reason or two-person rule)"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 404, description = "No active Linux agent with this id"),
+        (status = 409, description = "The agent already has an acquisition pending or running"),
+        (status = 503, description = "Memory acquisition off (setting or feature flag for the agent's tenant), admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_request_acquisition(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<AcquisitionRequest>,
+) -> Result<Json<AcquisitionRequestResponse>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    let policy = acquisition_policy(&state)?;
+    if let Err(reason) = policy.check(&req) {
+        warn!("Refused memory acquisition for agent {}: {}", req.agent_id, reason);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let db = control_db(&state)?;
+    memory_acquisition::expire_stale(db, &policy.spool_dir)
+        .await
+        .map_err(db_err("Failed to expire stale memory acquisitions"))?;
+    let agent = db
+        .query_opt(
+            "SELECT tenant_id FROM agents WHERE agent_id = $1 AND agent_type::text = 'linux_agent' AND is_active",
+            &[&req.agent_id],
+        )
+        .await
+        .map_err(db_err("Failed to look up agent"))?;
+    let Some(agent) = agent else {
+        warn!("Refused memory acquisition: {} is not an active Linux agent", req.agent_id);
+        return Err(StatusCode::NOT_FOUND);
+    };
+    let tenant: Option<String> = agent.get(0);
+    if !state.feature_flags.is_enabled(Flag::MemoryAcquisition, tenant.as_deref()) {
+        warn!("Refused memory acquisition for agent {}: feature flag {} is off", req.agent_id, Flag::MemoryAcquisition.as_str());
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    let acquisition_id = Uuid::new_v4();
+    let inserted = db
+        .execute(
+            r#"
+            INSERT INTO memory_acquisitions (
+                acquisition_id, agent_id, incident_id, scope, pid, reason, requested_by, approved_by,
+                max_bytes, chunk_bytes
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            "#,
+            &[
+                &acquisition_id,
+                &req.agent_id,
+                &req.incident_id,
+                &req.scope.as_str(),
+                &req.pid,
+                &req.reason,
+                &req.requested_by,
+                &req.approved_by,
+                &(policy.max_bytes as i64),
+                &(policy.chunk_bytes as i64),
+            ],
+        )
+        .await;
+    if let Err(e) = inserted {
+        if e.code() == Some(&SqlState::UNIQUE_VIOLATION) {
+            warn!("Refused memory acquisition: agent {} already has one pending or running", req.agent_id);
+            return Err(StatusCode::CONFLICT);
+        }
+        return Err(db_err("Failed to insert memory acquisition")(e));
+    }
+
+    http_agent_auth::audit(
+        state.store.as_ref(),
+        None,
+        "MEMORY_ACQUISITION_REQUESTED",
+        Some(acquisition_id),
+        &serde_json::json!({
+            "acquisition_id": acquisition_id.to_string(),
+            "agent_id": req.agent_id.to_string(),
+            "incident_id": req.incident_id.map(|id| id.to_string()),
+            "scope": req.scope.as_str(),
+            "pid": req.pid,
+            "reason": req.reason,
+            "requested_by": req.requested_by,
+            "approved_by": req.approved_by,
+            "max_bytes": policy.max_bytes,
+        }),
+    )
+    .await?;
+    info!(
+        "Memory acquisition requested | acquisition_id={} | agent_id={} | scope={} | by={} | approved_by={}",
+        acquisition_id,
+        req.agent_id,
+        req.scope.as_str(),
+        req.requested_by,
+        req.approved_by
+    );
+    Ok(Json(AcquisitionRequestResponse { acquisition_id: acquisition_id.to_string() }))
+}
+
+/// GET /admin/memory-acquisitions (X-Admin-Key): acquisition requests and their outcome, oldest
+/// first, as a list page; filter[agent_id]=<id> selects the acquisitions of one host.
+#[utoipa::path(
+    get,
+    path = "/admin/memory-acquisitions",
+    tag = "admin",
+    params(
+        ("limit" = Option<usize>, Query, description = "Page size (1-500, default 50)"),
+        ("cursor" = Option<String>, Query, description = "next_cursor of the previous page"),
+        ("fields" = Option<String>, Query, description = "Comma-separated sparse fieldset"),
+    ),
+    responses(
+        (status = 200, description = "Page of acquisitions (MemoryAcquisition items)", body = Page),
+        (status = 400, description = "Unsupported list parameter"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_list_acquisitions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<ListQuery, ListQueryRejection>,
+) -> Result<Json<Page>, Response> {
+    state.admin_key.check(&headers).map_err(IntoResponse::into_response)?;
+    let query = query.map_err(IntoResponse::into_response)?;
+    let db = control_db(&state).map_err(IntoResponse::into_response)?;
+    let rows = db
+        .query(
+            r#"
+            SELECT acquisition_id, agent_id, incident_id, scope, pid, reason, requested_by, approved_by, status,
+                   requested_at, claimed_at, last_activity_at, finished_at, chunks_received, bytes_received,
+                   image_sha256, tool, failure_reason
+            FROM memory_acquisitions
+            "#,
+            &[],
+        )
+        .await
+        .map_err(db_err("Failed to list memory acquisitions"))
+        .map_err(IntoResponse::into_response)?;
+    let acquisitions: Vec<MemoryAcquisition> = rows
+        .iter()
+        .map(|r| MemoryAcquisition {
+            acquisition_id: r.get(0),
+            agent_id: r.get(1),
+            incident_id: r.get(2),
+            scope: r.get(3),
+            pid: r.get(4),
+            reason: r.get(5),
+            requested_by: r.get(6),
+            approved_by: r.get(7),
+            status: r.get(8),
+            requested_at: r.get(9),
+            claimed_at: r.get(10),
+            last_activity_at: r.get(11),
+            finished_at: r.get(12),
+            chunks_received: r.get(13),
+            bytes_received: r.get(14),
+            image_sha256: r.get::<_, Option<Vec<u8>>>(15).map(hex::encode),
+            tool: r.get(16),
+            failure_reason: r.get(17),
+        })
+        .collect();
+    query
+        .paginate(&ACQUISITION_LIST, acquisitions, |a| micros_key(a.requested_at, a.acquisition_id))
+        .map(Json)
+        .map_err(IntoResponse::into_response)
+}
+
+/// POST /agents/memory-acquisitions/claim (Bearer, Linux agent): take this agent's pending
+/// acquisition request, if any.
+#[utoipa::path(
+    post,
+    path = "/agents/memory-acquisitions/claim",
+    tag = "agents",
+    responses(
+        (status = 200, description = "Acquisition to run now", body = AcquisitionOrder),
+        (status = 204, description = "No acquisition requested for this agent, or the memory_acquisition feature flag is off for it"),
+        (status = 401, description = "Missing or invalid agent token"),
+        (status = 403, description = "Agent is not a Linux agent"),
+        (status = 503, description = "Memory acquisition off or postgres control plane not configured"),
+    ),
+    security(("agent_token" = []))
+)]
+pub async fn handle_claim_acquisition(
+    State(state): State<AppState>,
+    auth: Option<Extension<AuthenticatedAgent>>,
+) -> Result<Response, StatusCode> {
+    let auth = linux_agent(auth)?;
+    let policy = acquisition_policy(&state)?;
+    let db = control_db(&state)?;
+    memory_acquisition::expire_stale(db, &policy.spool_dir)
+        .await
+        .map_err(db_err("Failed to expire stale memory acquisitions"))?;
+    // Switched off: the request stays pending until the flag is back on or it expires
+    if !state.feature_flags.is_enabled(Flag::MemoryAcquisition, auth.tenant_id.as_deref()) {
+        return Ok(StatusCode::NO_CONTENT.into_response());
+    }
+    let row = db
+        .query_opt(
+            r#"
+            UPDATE memory_acquisitions
+            SET status = 'acquiring', claimed_at = now(), last_activity_at = now()
+            WHERE agent_id = $1 AND status = 'requested'
+            RETURNING acquisition_id, scope, pid, max_bytes, chunk_bytes
+            "#,
+            &[&auth.agent_id],
+        )
+        .await
+        .map_err(db_err("Failed to claim memory acquisition"))?;
+    let Some(row) = row else {
+        return Ok(StatusCode::NO_CONTENT.into_response());
+    };
+    let scope: String = row.get(1);
+    let scope = AcquisitionScope::parse(&scope).ok_or_else(|| {
+        error!("FAIL-CLOSED: memory_acquisitions has unknown scope '{}'", scope);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let order = AcquisitionOrder {
+        acquisition_id: row.get(0),
+        scope,
+        pid: row.get(2),
+        max_bytes: row.get(3),
+        chunk_bytes: row.get(4),
+    };
+
+    http_agent_auth::audit(
+        state.store.as_ref(),
+        Some(auth.agent_id),
+        "MEMORY_ACQUISITION_CLAIMED",
+        Some(order.acquisition_id),
+        &serde_json::json!({
+            "acquisition_id": order.acquisition_id.to_string(),
+            "agent_id": auth.agent_id.to_string(),
+            "component_identity": auth.component_identity,
+            "scope": order.scope.as_str(),
+            "pid": order.pid,
+        }),
+    )
+    .await?;
+    info!(
+        "Memory acquisition claimed | acquisition_id={} | agent={} | scope={}",
+        order.acquisition_id,
+        auth.component_identity,
+        order.scope.as_str()
+    );
+    Ok(Json(order).into_response())
+}
+
+/// POST /agents/memory-acquisitions/chunk (Bearer, Linux agent): the next chunk of a claimed
+/// acquisition, named by X-Acquisition-Id and X-Chunk-Index with its SHA-256 in X-Content-SHA256.
+/// Chunks arrive in order; resending the last recorded chunk unchanged is accepted.
+#[utoipa::path(
+    post,
+    path = "/agents/memory-acquisitions/chunk",
+    tag = "agents",
+    request_body(content = Vec<u8>, content_type = "application/octet-stream"),
+    params(
+        ("X-Acquisition-Id" = Uuid, Header, description = "acquisition_id of the claimed acquisition"),
+        ("X-Chunk-Index" = u32, Header, description = "Zero-based position of the chunk in the image"),
+        ("X-Content-SHA256" = String, Header, description = "Hex SHA-256 of the body"),
+    ),
+    responses(
+        (status = 200, description = "Chunk stored", body = ChunkUploadResponse),
+        (status = 400, description = "Missing headers, SHA-256 mismatch or empty chunk"),
+        (status = 401, description = "Missing or invalid agent token"),
+        (status = 403, description = "Agent is not a Linux agent"),
+        (status = 404, description = "No acquisition in progress with this id for this agent"),
+        (status = 409, description = "Chunk out of order"),
+        (status = 413, description = "Chunk larger than chunk_bytes or image larger than max_bytes"),
+        (status = 503, description = "Memory acquisition off or postgres control plane not configured"),
+        (status = 507, description = "Memory spool over its disk quota; retry after Retry-After seconds"),
+    ),
+    security(("agent_token" = []))
+)]
+pub async fn handle_upload_chunk(
+    State(state): State<AppState>,
+    auth: Option<Extension<AuthenticatedAgent>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<ChunkUploadResponse>, StatusCode> {
+    let auth = linux_agent(auth)?;
+    let policy = acquisition_policy(&state)?;
+    let db = control_db(&state)?;
+    let acquisition_id =
+        Uuid::parse_str(header(&headers, ACQUISITION_ID_HEADER)?).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let chunk_index: u32 = header(&headers, CHUNK_INDEX_HEADER)?.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+    let claimed_sha256 = header(&headers, CONTENT_SHA256_HEADER)?.to_ascii_lowercase();
+    if body.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let row = db
+        .query_opt(
+            r#"
+            SELECT chunks_received, bytes_received, max_bytes, chunk_bytes,
+                   (SELECT sha256 FROM memory_acquisition_chunks c
+                    WHERE c.acquisition_id = a.acquisition_id AND c.chunk_index = a.chunks_received - 1)
+            FROM memory_acquisitions a
+            WHERE acquisition_id = $1 AND agent_id = $2 AND status = 'acquiring'
+            "#,
+            &[&acquisition_id, &auth.agent_id],
+        )
+        .await
+        .map_err(db_err("Failed to look up memory acquisition"))?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let chunks_received: i32 = row.get(0);
+    let bytes_received: i64 = row.get(1);
+    let max_bytes: i64 = row.get(2);
+    let chunk_bytes: i64 = row.get(3);
+    let last_sha256: Option<Vec<u8>> = row.get(4);
+
+    let chunk_sha256 = hex::encode(Sha256::digest(&body));
+    if chunk_sha256 != claimed_sha256 {
+        warn!(
+            "Rejected memory chunk {}#{}: SHA-256 {} does not match X-Content-SHA256 {}",
+            acquisition_id, chunk_index, chunk_sha256, claimed_sha256
+        );
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    // A retry of the chunk already recorded (its response was lost)
+    if chunk_index as i64 + 1 == chunks_received as i64 {
+        if last_sha256.map(hex::encode).as_deref() == Some(chunk_sha256.as_str()) {
+            return Ok(Json(ChunkUploadResponse {
+                acquisition_id: acquisition_id.to_string(),
+                chunk_index,
+                chunk_sha256,
+                chunks_received,
+            }));
+        }
+        return Err(StatusCode::CONFLICT);
+    }
+    if chunk_index as i64 != chunks_received as i64 {
+        warn!("Rejected memory chunk {}#{}: expected chunk {}", acquisition_id, chunk_index, chunks_received);
+        return Err(StatusCode::CONFLICT);
+    }
+    if body.len() as i64 > chunk_bytes || bytes_received + body.len() as i64 > max_bytes {
+        warn!(
+            "Rejected memory chunk {}#{}: {} bytes exceed chunk_bytes {} or max_bytes {}",
+            acquisition_id,
+            chunk_index,
+            body.len(),
+            chunk_bytes,
+            max_bytes
+        );
+        return Err(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+    state.disk_quotas.admit(disk_quota::MEMORY_SPOOL, body.len() as u64)?;
+
+    // Spool first: the chunk row only exists once the bytes are on disk
+    let spool_dir = policy.spool_dir.clone();
+    let spooled = {
+        let (spool_dir, body) = (spool_dir.clone(), body.clone());
+        tokio::task::spawn_blocking(move || {
+            memory_acquisition::write_chunk(&spool_dir, acquisition_id, chunk_index, &body)
+        })
+        .await
+    };
+    match spooled {
+        Ok(Ok(())) => state.disk_quotas.record_write(disk_quota::MEMORY_SPOOL, body.len() as u64),
+        Ok(Err(e)) => {
+            error!("FAIL-CLOSED: Failed to spool memory chunk {}#{} in {}: {}", acquisition_id, chunk_index, spool_dir.display(), e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+        Err(e) => {
+            error!("FAIL-CLOSED: memory chunk spool task failed for {}#{}: {}", acquisition_id, chunk_index, e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+
+    // One statement: the chunk row and the acquisition counters move together
+    let recorded = db
+        .execute(
+            r#"
+            WITH acquisition AS (
+                UPDATE memory_acquisitions
+                SET chunks_received = chunks_received + 1,
+                    bytes_received = bytes_received + $4,
+                    last_activity_at = now()
+                WHERE acquisition_id = $1 AND agent_id = $2 AND status = 'acquiring'
+                  AND chunks_received = $3 AND bytes_received + $4 <= max_bytes
+                RETURNING acquisition_id
+            )
+            INSERT INTO memory_acquisition_chunks (acquisition_id, chunk_index, sha256, bytes)
+            SELECT acquisition_id, $3, $5, $4 FROM acquisition
+            "#,
+            &[
+                &acquisition_id,
+                &auth.agent_id,
+                &(chunk_index as i32),
+                &(body.len() as i64),
+                &hex::decode(&chunk_sha256).unwrap_or_default(),
+            ],
+        )
+        .await
+        .map_err(db_err("Failed to record memory chunk"))?;
+    if recorded == 0 {
+        // Expired, failed or raced by a concurrent upload of the same chunk
+        warn!("Rejected memory chunk {}#{}: acquisition moved on", acquisition_id, chunk_index);
+        return Err(StatusCode::CONFLICT);
+    }
+    Ok(Json(ChunkUploadResponse {
+        acquisition_id: acquisition_id.to_string(),
+        chunk_index,
+        chunk_sha256,
+        chunks_received: chunks_received + 1,
+    }))
+}
+
+/// POST /agents/memory-acquisitions/complete (Bearer, Linux agent): all chunks are sent. Ingest
+/// re-reads the spooled chunks, checks the image against the agent's SHA-256 and writes the
+/// chain-of-custody manifest for the evidence store.
+#[utoipa::path(
+    post,
+    path = "/agents/memory-acquisitions/complete",
+    tag = "agents",
+    request_body = AcquisitionCompleteRequest,
+    responses(
+        (status = 200, description = "Image verified and spooled", body = AcquisitionCompleteResponse),
+        (status = 400, description = "Chunk count, size or SHA-256 does not match what was received"),
+        (status = 401, description = "Missing or invalid agent token"),
+        (status = 403, description = "Agent is not a Linux agent"),
+        (status = 404, description = "No acquisition in progress with this id for this agent"),
+        (status = 503, description = "Memory acquisition off or postgres control plane not configured"),
+    ),
+    security(("agent_token" = []))
+)]
+pub async fn handle_complete_acquisition(
+    State(state): State<AppState>,
+    auth: Option<Extension<AuthenticatedAgent>>,
+    Json(req): Json<AcquisitionCompleteRequest>,
+) -> Result<Json<AcquisitionCompleteResponse>, StatusCode> {
+    let auth = linux_agent(auth)?;
+    let policy = acquisition_policy(&state)?;
+    let db = control_db(&state)?;
+    let image_sha256 = req.image_sha256.trim().to_ascii_lowercase();
+    if !is_sha256_hex(&image_sha256)
+        || req.tool.trim().is_empty()
+        || req.tool.len() > MAX_TEXT_BYTES
+        || req.tool_version.as_ref().is_some_and(|v| v.len() > MAX_TEXT_BYTES)
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let row = db
+        .query_opt(
+            r#"
+            SELECT incident_id, scope, pid, reason, requested_by, approved_by, requested_at, claimed_at,
+                   chunks_received, bytes_received
+            FROM memory_acquisitions
+            WHERE acquisition_id = $1 AND agent_id = $2 AND status = 'acquiring'
+            "#,
+            &[&req.acquisition_id, &auth.agent_id],
+        )
+        .await
+        .map_err(db_err("Failed to look up memory acquisition"))?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let chunks_received: i32 = row.get(8);
+    let bytes_received: i64 = row.get(9);
+    if chunks_received == 0 || req.chunk_count as i64 != chunks_received as i64 || req.image_bytes as i64 != bytes_received {
+        warn!(
+            "Rejected memory acquisition completion {}: agent sent {} chunk(s)/{} bytes, {}/{} received",
+            req.acquisition_id, req.chunk_count, req.image_bytes, chunks_received, bytes_received
+        );
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let chunks: Vec<ChunkEntry> = db
+        .query(
+            r#"
+            SELECT chunk_index, sha256, bytes FROM memory_acquisition_chunks
+            WHERE acquisition_id = $1
+            ORDER BY chunk_index
+            "#,
+            &[&req.acquisition_id],
+        )
+        .await
+        .map_err(db_err("Failed to read memory chunks"))?
+        .iter()
+        .map(|r| ChunkEntry {
+            index: r.get::<_, i32>(0) as u32,
+            sha256: hex::encode(r.get::<_, Vec<u8>>(1)),
+            bytes: r.get::<_, i64>(2) as u64,
+        })
+        .collect();
+
+    let spool_dir = policy.spool_dir.clone();
+    let verified = {
+        let (spool_dir, chunks) = (spool_dir.clone(), chunks.clone());
+        let acquisition_id = req.acquisition_id;
+        tokio::task::spawn_blocking(move || memory_acquisition::verify_chunks(&spool_dir, acquisition_id, &chunks)).await
+    };
+    let (actual_sha256, actual_bytes) = match verified {
+        Ok(Ok(image)) => image,
+        Ok(Err(reason)) => {
+            error!("FAIL-CLOSED: spooled memory image {} failed verification: {}", req.acquisition_id, reason);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+        Err(e) => {
+            error!("FAIL-CLOSED: memory verification task failed for {}: {}", req.acquisition_id, e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    if actual_sha256 != image_sha256 || actual_bytes != req.image_bytes {
+        warn!(
+            "Rejected memory acquisition completion {}: image SHA-256 {} does not match {} sent by the agent",
+            req.acquisition_id, actual_sha256, image_sha256
+        );
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let scope: String = row.get(1);
+    let scope = AcquisitionScope::parse(&scope).ok_or_else(|| {
+        error!("FAIL-CLOSED: memory_acquisitions has unknown scope '{}'", scope);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let requested_by: String = row.get(4);
+    let approved_by: String = row.get(5);
+    let requested_at: DateTime<Utc> = row.get(6);
+    let claimed_at: DateTime<Utc> = row.get(7);
+    let completed_at = Utc::now();
+    let tool_detail = match &req.tool_version {
+        Some(version) => format!("{} {}", req.tool, version),
+        None => req.tool.clone(),
+    };
+    let manifest = SpoolManifest {
+        acquisition_id: req.acquisition_id,
+        agent_id: auth.agent_id,
+        agent_component_id: auth.component_identity.clone(),
+        incident_id: row.get(0),
+        scope,
+        pid: row.get(2),
+        reason: row.get(3),
+        requested_by: requested_by.clone(),
+        approved_by: approved_by.clone(),
+        requested_at,
+        claimed_at,
+        completed_at,
+        tool: req.tool.clone(),
+        tool_version: req.tool_version.clone(),
+        image_sha256: image_sha256.clone(),
+        image_bytes: req.image_bytes,
+        chunk_dir: req.acquisition_id.to_string(),
+        custody: vec![
+            CustodyEntry { at: requested_at, action: "requested".to_string(), actor: requested_by, detail: "operator request".to_string() },
+            CustodyEntry { at: requested_at, action: "approved".to_string(), actor: approved_by, detail: "second-person approval".to_string() },
+            CustodyEntry {
+                at: claimed_at,
+                action: "claimed".to_string(),
+                actor: auth.component_identity.clone(),
+                detail: format!("agent {}", auth.agent_id),
+            },
+            CustodyEntry {
+                at: completed_at,
+                action: "acquired".to_string(),
+                actor: auth.component_identity.clone(),
+                detail: format!("{} chunk(s), {} bytes, tool {}", chunks.len(), req.image_bytes, tool_detail),
+            },
+            CustodyEntry {
+                at: completed_at,
+                action: "verified".to_string(),
+                actor: "ingest".to_string(),
+                detail: format!("chunk and image SHA-256 {} checked", image_sha256),
+            },
+        ],
+        chunks,
+    };
+    let written = {
+        let (spool_dir, manifest) = (spool_dir.clone(), manifest.clone());
+        tokio::task::spawn_blocking(move || memory_acquisition::write_manifest(&spool_dir, &manifest)).await
+    };
+    match written {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => {
+            error!("FAIL-CLOSED: Failed to write memory manifest {} in {}: {}", req.acquisition_id, spool_dir.display(), e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+        Err(e) => {
+            error!("FAIL-CLOSED: memory manifest task failed for {}: {}", req.acquisition_id, e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+
+    let updated = db
+        .execute(
+            r#"
+            UPDATE memory_acquisitions
+            SET status = 'acquired', finished_at = $3, image_sha256 = $4, tool = $5
+            WHERE acquisition_id = $1 AND agent_id = $2 AND status = 'acquiring'
+            "#,
+            &[
+                &req.acquisition_id,
+                &auth.agent_id,
+                &completed_at,
+                &hex::decode(&image_sha256).unwrap_or_default(),
+                &tool_detail,
+            ],
+        )
+        .await;
+    match updated {
+        Ok(1) => {}
+        Ok(_) => {
+            // Expired or failed meanwhile: nothing may reach the evidence store without its row
+            warn!("Rejected memory acquisition completion {}: acquisition is no longer in progress", req.acquisition_id);
+            memory_acquisition::remove_spool(&spool_dir, req.acquisition_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(spool_dir.join(format!("{}.json", req.acquisition_id)));
+            return Err(db_err("Failed to record memory acquisition completion")(e));
+        }
+    }
+
+    http_agent_auth::audit(
+        state.store.as_ref(),
+        Some(auth.agent_id),
+        "MEMORY_ACQUISITION_COMPLETED",
+        Some(req.acquisition_id),
+        &serde_json::json!({
+            "acquisition_id": req.acquisition_id.to_string(),
+            "agent_id": auth.agent_id.to_string(),
+            "incident_id": manifest.incident_id.map(|id| id.to_string()),
+            "scope": manifest.scope.as_str(),
+            "pid": manifest.pid,
+            "tool": tool_detail,
+            "chunks": manifest.chunks.len(),
+            "image_bytes": manifest.image_bytes,
+            "image_sha256": image_sha256,
+        }),
+    )
+    .await?;
+    info!(
+        "Memory acquisition completed | acquisition_id={} | agent={} | bytes={} | sha256={}",
+        req.acquisition_id, auth.component_identity, manifest.image_bytes, image_sha256
+    );
+    Ok(Json(AcquisitionCompleteResponse {
+        acquisition_id: req.acquisition_id.to_string(),
+        image_sha256,
+        image_bytes: manifest.image_bytes,
+    }))
+}
+
+/// POST /agents/memory-acquisitions/fail (Bearer, Linux agent): a claimed acquisition could not
+/// be taken (acquisition disabled on the host, scope refused, tool error). Spooled chunks are dropped.
+#[utoipa::path(
+    post,
+    path = "/agents/memory-acquisitions/fail",
+    tag = "agents",
+    request_body = AcquisitionFailRequest,
+    responses(
+        (status = 200, description = "Acquisition marked failed", body = AcquisitionFailResponse),
+        (status = 400, description = "Empty or overlong reason"),
+        (status = 401, description = "Missing or invalid agent token"),
+        (status = 403, description = "Agent is not a Linux agent"),
+        (status = 404, description = "No acquisition in progress with this id for this agent"),
+        (status = 503, description = "Memory acquisition off or postgres control plane not configured"),
+    ),
+    security(("agent_token" = []))
+)]
+pub async fn handle_fail_acquisition(
+    State(state): State<AppState>,
+    auth: Option<Extension<AuthenticatedAgent>>,
+    Json(req): Json<AcquisitionFailRequest>,
+) -> Result<Json<AcquisitionFailResponse>, StatusCode> {
+    let auth = linux_agent(auth)?;
+    let policy = acquisition_policy(&state)?;
+    if req.reason.trim().is_empty() || req.reason.len() > MAX_TEXT_BYTES {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let updated = control_db(&state)?
+        .execute(
+            r#"
+            UPDATE memory_acquisitions
+            SET status = 'failed', finished_at = now(), failure_reason = $3
+            WHERE acquisition_id = $1 AND agent_id = $2 AND status = 'acquiring'
+            "#,
+            &[&req.acquisition_id, &auth.agent_id, &req.reason],
+        )
+        .await
+        .map_err(db_err("Failed to record memory acquisition failure"))?;
+    if updated == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    memory_acquisition::remove_spool(&policy.spool_dir, req.acquisition_id);
+
+    http_agent_auth::audit(
+        state.store.as_ref(),
+        Some(auth.agent_id),
+        "MEMORY_ACQUISITION_FAILED",
+        Some(req.acquisition_id),
+        &serde_json::json!({
+            "acquisition_id": req.acquisition_id.to_string(),
+            "agent_id": auth.agent_id.to_string(),
+            "reason": req.reason,
+        }),
+    )
+    .await?;
+    warn!(
+        "Memory acquisition failed | acquisition_id={} | agent={} | reason={}",
+        req.acquisition_id, auth.component_identity, req.reason
+    );
+    Ok(Json(AcquisitionFailResponse { updated }))
+}
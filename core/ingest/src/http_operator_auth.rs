@@ -0,0 +1,320 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/http_operator_auth.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Operator sign-in endpoints - OIDC authorization code login for the dashboard (PKCE, signed login cookie), break-glass login with mandatory reason and audit, and the current session
+
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Json, Redirect, Response},
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{error, info, warn};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::http_agent_auth;
+use crate::http_server::AppState;
+use crate::oidc::{self, OidcProvider};
+use crate::operator_auth::{self, OperatorAuthError, OperatorAuthMethod, OperatorIdentity, SessionSigner};
+use crate::webhooks;
+
+const LOGIN_COOKIE: &str = "ransomeye_oidc_login";
+const LOGIN_STATE_KIND: &str = "reo1";
+/// A dashboard login must complete within this many seconds
+const LOGIN_TTL_SECS: i64 = 600;
+/// Break-glass reasons shorter than this are refused
+const MIN_REASON_LEN: usize = 10;
+
+/// Pending dashboard login, sealed into the login cookie.
+#[derive(Serialize, Deserialize)]
+struct LoginState {
+    state: String,
+    nonce: String,
+    code_verifier: String,
+    expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct OidcCallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    /// Set by the IdP when the user or policy refused the login
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BreakGlassLoginRequest {
+    pub username: String,
+    pub password: String,
+    /// Why the IdP cannot be used (recorded with every request of the session)
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct OperatorSessionResponse {
+    /// Bearer token for the admin API
+    pub session_token: String,
+    pub operator: OperatorIdentity,
+}
+
+fn random_token() -> Result<String, StatusCode> {
+    let mut bytes = [0u8; 32];
+    crypto::rand::fill(&mut bytes).map_err(|e| {
+        error!("FAIL-CLOSED: random generation failed: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+fn login_parts(state: &AppState) -> Result<(&OidcProvider, &SessionSigner), StatusCode> {
+    match (&state.operator_auth.oidc, &state.operator_auth.sessions) {
+        (Some(provider), Some(sessions)) if provider.config().redirect_url.is_some() => Ok((provider, sessions)),
+        _ => {
+            warn!("Dashboard OIDC login requested but RANSOMEYE_OIDC_REDIRECT_URL is not configured");
+            Err(StatusCode::SERVICE_UNAVAILABLE)
+        }
+    }
+}
+
+fn login_cookie(value: &str, max_age: i64) -> Result<HeaderValue, StatusCode> {
+    HeaderValue::from_str(&format!(
+        "{}={}; Path=/auth/oidc; HttpOnly; Secure; SameSite=Lax; Max-Age={}",
+        LOGIN_COOKIE, value, max_age
+    ))
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+fn cookie<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|c| c.trim().split_once('='))
+        .find(|(k, _)| *k == name)
+        .map(|(_, v)| v)
+}
+
+/// GET /auth/oidc/login: start a dashboard login - redirect to the IdP with state, nonce and a
+/// PKCE challenge; the verifier travels in a signed, HttpOnly login cookie.
+#[utoipa::path(
+    get,
+    path = "/auth/oidc/login",
+    tag = "auth",
+    responses(
+        (status = 303, description = "Redirect to the identity provider"),
+        (status = 503, description = "Dashboard login not configured or IdP discovery failed"),
+    )
+)]
+pub async fn handle_oidc_login(State(state): State<AppState>) -> Result<Response, StatusCode> {
+    let (provider, sessions) = login_parts(&state)?;
+    let login = LoginState {
+        state: random_token()?,
+        nonce: random_token()?,
+        code_verifier: random_token()?,
+        expires_at: Utc::now() + chrono::Duration::seconds(LOGIN_TTL_SECS),
+    };
+    let url = provider
+        .authorization_url(&login.state, &login.nonce, &oidc::pkce_challenge(&login.code_verifier))
+        .await
+        .map_err(|e| {
+            error!("OIDC login unavailable: {}", e);
+            StatusCode::SERVICE_UNAVAILABLE
+        })?;
+    let mut response = Redirect::to(url.as_str()).into_response();
+    response
+        .headers_mut()
+        .insert(header::SET_COOKIE, login_cookie(&sessions.seal(LOGIN_STATE_KIND, &login), LOGIN_TTL_SECS)?);
+    Ok(response)
+}
+
+/// GET /auth/oidc/callback: finish a dashboard login - exchange the code, validate the ID token
+/// and map the user's groups to a role. Redirects to RANSOMEYE_OIDC_DASHBOARD_URL with the
+/// session in the fragment, or returns it as JSON without one.
+#[utoipa::path(
+    get,
+    path = "/auth/oidc/callback",
+    tag = "auth",
+    params(OidcCallbackQuery),
+    responses(
+        (status = 200, description = "Operator session (no dashboard URL configured)", body = OperatorSessionResponse),
+        (status = 303, description = "Redirect to the dashboard with #session=<token>"),
+        (status = 400, description = "Missing code/state or the IdP reported an error"),
+        (status = 401, description = "Login cookie missing, expired or state mismatch; ID token rejected"),
+        (status = 403, description = "No role mapped for the user's groups"),
+        (status = 503, description = "Dashboard login not configured or the IdP is unreachable"),
+    )
+)]
+pub async fn handle_oidc_callback(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<OidcCallbackQuery>,
+) -> Result<Response, StatusCode> {
+    let (provider, sessions) = login_parts(&state)?;
+    if let Some(error) = &query.error {
+        warn!("OIDC login refused by the identity provider: {}", error);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let (Some(code), Some(returned_state)) = (&query.code, &query.state) else {
+        return Err(StatusCode::BAD_REQUEST);
+    };
+    let login: LoginState = cookie(&headers, LOGIN_COOKIE)
+        .ok_or_else(|| OperatorAuthError::Malformed("no login cookie".to_string()))
+        .and_then(|c| sessions.open(LOGIN_STATE_KIND, c))
+        .map_err(|e| {
+            warn!("OIDC callback rejected: {}", e);
+            StatusCode::UNAUTHORIZED
+        })?;
+    let now = Utc::now();
+    if login.expires_at <= now || !crypto::constant_time_eq(login.state.as_bytes(), returned_state.as_bytes()) {
+        warn!("OIDC callback rejected: login expired or state mismatch");
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    let id_token = provider.exchange_code(code, &login.code_verifier).await.map_err(|e| {
+        error!("OIDC code exchange failed: {}", e);
+        StatusCode::SERVICE_UNAVAILABLE
+    })?;
+    let verified = provider.validate_id_token(&id_token, &login.nonce, now).await.map_err(|e| {
+        warn!("OIDC ID token rejected: {}", e);
+        OperatorAuthError::Token(e).status()
+    })?;
+    let identity = match state.operator_auth.identity_for(&verified, now, Some(Uuid::new_v4())) {
+        Ok(identity) => identity,
+        Err(e) => {
+            warn!("OIDC login denied: {}", e);
+            let payload = json!({ "subject": verified.subject, "name": verified.name, "groups": verified.groups });
+            http_agent_auth::audit(state.store.as_ref(), None, "OPERATOR_OIDC_LOGIN_DENIED", None, &payload).await?;
+            return Err(e.status());
+        }
+    };
+
+    let payload = json!({
+        "subject": identity.subject,
+        "name": identity.name,
+        "role": identity.role.as_str(),
+        "groups": verified.groups,
+        "session_id": identity.session_id,
+        "expires_at": identity.expires_at,
+    });
+    http_agent_auth::audit(state.store.as_ref(), None, "OPERATOR_OIDC_LOGIN", identity.session_id, &payload).await?;
+    info!("Operator '{}' signed in via OIDC as {}", identity.name, identity.role.as_str());
+
+    let session_token = sessions.issue(&identity);
+    let mut response = match &state.operator_auth.dashboard_url {
+        Some(dashboard) => {
+            let mut target = dashboard.clone();
+            target.set_fragment(Some(&format!("session={}", session_token)));
+            Redirect::to(target.as_str()).into_response()
+        }
+        None => Json(OperatorSessionResponse { session_token, operator: identity }).into_response(),
+    };
+    response.headers_mut().insert(header::SET_COOKIE, login_cookie("", 0)?);
+    Ok(response)
+}
+
+/// POST /auth/break-glass: local emergency login for when the IdP is unavailable. Every attempt
+/// and every request of the session is audited; repeated failures lock the account.
+#[utoipa::path(
+    post,
+    path = "/auth/break-glass",
+    tag = "auth",
+    request_body = BreakGlassLoginRequest,
+    responses(
+        (status = 200, description = "Break-glass session issued", body = OperatorSessionResponse),
+        (status = 400, description = "Missing reason"),
+        (status = 401, description = "Invalid credentials"),
+        (status = 429, description = "Account locked after repeated failures"),
+        (status = 503, description = "No break-glass accounts configured"),
+    )
+)]
+pub async fn handle_break_glass_login(
+    State(state): State<AppState>,
+    Json(req): Json<BreakGlassLoginRequest>,
+) -> Result<Json<OperatorSessionResponse>, StatusCode> {
+    let (Some(break_glass), Some(sessions)) = (&state.operator_auth.break_glass, &state.operator_auth.sessions) else {
+        warn!("Break-glass login attempted but RANSOMEYE_BREAK_GLASS_ACCOUNTS_PATH is not configured");
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    };
+    let reason = req.reason.trim();
+    if reason.chars().count() < MIN_REASON_LEN {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let role = match break_glass.authenticate(&req.username, &req.password, std::time::Instant::now()) {
+        Ok(role) => role,
+        Err(e) => {
+            error!("BREAK-GLASS login FAILED for '{}': {}", req.username, e);
+            let payload = json!({ "username": req.username, "reason": reason, "error": e.to_string() });
+            http_agent_auth::audit(state.store.as_ref(), None, "OPERATOR_BREAK_GLASS_LOGIN_FAILED", None, &payload).await?;
+            return Err(e.status());
+        }
+    };
+
+    let now = Utc::now();
+    let identity = OperatorIdentity {
+        subject: req.username.clone(),
+        name: req.username.clone(),
+        role,
+        method: OperatorAuthMethod::BreakGlass,
+        expires_at: now + chrono::Duration::from_std(break_glass.session_ttl).unwrap_or(chrono::Duration::minutes(15)),
+        session_id: Some(Uuid::new_v4()),
+        reason: Some(reason.to_string()),
+    };
+    let payload = json!({
+        "username": identity.subject,
+        "role": role.as_str(),
+        "reason": reason,
+        "session_id": identity.session_id,
+        "expires_at": identity.expires_at,
+    });
+    // FAIL-CLOSED: no session without its audit row
+    http_agent_auth::audit(state.store.as_ref(), None, "OPERATOR_BREAK_GLASS_LOGIN", identity.session_id, &payload).await?;
+    error!(
+        "BREAK-GLASS login: '{}' as {} until {} | reason: {}",
+        identity.subject,
+        role.as_str(),
+        identity.expires_at.to_rfc3339(),
+        reason
+    );
+
+    // The session is already audited; a lost notification must not fail the login
+    if let Some(db) = &state.db {
+        let event = webhooks::WebhookEvent::new(webhooks::OPERATOR_BREAK_GLASS, payload);
+        if let Err(e) = webhooks::enqueue(db, &event).await {
+            error!("Failed to enqueue {} webhook for '{}': {}", webhooks::OPERATOR_BREAK_GLASS, identity.subject, e);
+        }
+    }
+
+    Ok(Json(OperatorSessionResponse { session_token: sessions.issue(&identity), operator: identity }))
+}
+
+/// GET /auth/session (Bearer): the operator the presented session or IdP token resolves to.
+#[utoipa::path(
+    get,
+    path = "/auth/session",
+    tag = "auth",
+    responses(
+        (status = 200, description = "Authenticated operator", body = OperatorIdentity),
+        (status = 401, description = "Missing, invalid or expired token"),
+        (status = 403, description = "No role mapped for the token's groups"),
+        (status = 503, description = "Operator authentication not configured"),
+    ),
+    security(("operator_bearer" = []))
+)]
+pub async fn handle_get_session(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<OperatorIdentity>, StatusCode> {
+    if !state.operator_auth.enabled() {
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+    let token = operator_auth::bearer(&headers).ok_or(StatusCode::UNAUTHORIZED)?;
+    state.operator_auth.authenticate(token, Utc::now()).await.map(Json).map_err(|e| {
+        warn!("OPERATOR AUTH REJECT: {}", e);
+        e.status()
+    })
+}
@@ -0,0 +1,467 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/http_pcap_capture.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: DPI probe control channel for triggered packet captures - probes claim capture requests, upload the resulting pcap or report a failure (agent bearer token, audited) - and the admin listing of capture requests
+
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+    Extension,
+};
+use axum::body::Bytes;
+use chrono::{DateTime, SecondsFormat, Utc};
+use crypto::digest::Sha256;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::disk_quota;
+use crate::http_agent_auth::{self, AuthenticatedAgent};
+use crate::feature_flags::Flag;
+use crate::http_list::{ListQuery, ListQueryRejection, ListSpec, Page};
+use crate::http_server::AppState;
+use crate::pcap_capture::{self, CaptureOrder, PcapCapture, PcapCaptureConfig, SpoolManifest};
+
+/// Upload header naming the capture
+pub const CAPTURE_ID_HEADER: &str = "x-capture-id";
+/// Upload header with the hex SHA-256 of the pcap
+pub const CONTENT_SHA256_HEADER: &str = "x-content-sha256";
+const MAX_FAILURE_REASON_BYTES: usize = 1024;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CaptureUploadResponse {
+    pub capture_id: String,
+    pub pcap_sha256: String,
+    pub pcap_bytes: u64,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CaptureFailRequest {
+    pub capture_id: Uuid,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CaptureFailResponse {
+    /// Rows changed (always 1; an unknown or finished capture is a 404)
+    pub updated: u64,
+}
+
+const CAPTURE_LIST: ListSpec = ListSpec {
+    filterable: &["detection_id", "incident_id", "host_ip", "status", "requested_at", "probe_agent_id", "finished_at"],
+    selectable: &[
+        "capture_id",
+        "detection_id",
+        "incident_id",
+        "host_ip",
+        "bpf_filter",
+        "duration_secs",
+        "max_bytes",
+        "snaplen",
+        "status",
+        "requested_at",
+        "probe_agent_id",
+        "claimed_at",
+        "finished_at",
+        "pcap_sha256",
+        "pcap_bytes",
+        "failure_reason",
+    ],
+};
+
+fn control_db(state: &AppState) -> Result<&Arc<Client>, StatusCode> {
+    state.db.as_ref().ok_or_else(|| {
+        error!("Packet capture operation requested but storage backend has no control plane (postgres required)");
+        StatusCode::SERVICE_UNAVAILABLE
+    })
+}
+
+fn capture_config(state: &AppState) -> Result<&PcapCaptureConfig, StatusCode> {
+    state.pcap_capture.as_deref().ok_or_else(|| {
+        warn!("Packet capture operation requested but triggered captures are off (RANSOMEYE_INGEST_PCAP_CAPTURE)");
+        StatusCode::SERVICE_UNAVAILABLE
+    })
+}
+
+fn db_err(what: &str) -> impl FnOnce(tokio_postgres::Error) -> StatusCode + '_ {
+    move |e| {
+        error!("FAIL-CLOSED: {}: {}", what, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+/// The control channel is for token-authenticated DPI probes only.
+fn probe(auth: Option<Extension<AuthenticatedAgent>>) -> Result<AuthenticatedAgent, StatusCode> {
+    let Some(Extension(auth)) = auth else {
+        warn!("AUTH REJECT: packet capture control channel requires an agent token");
+        return Err(StatusCode::UNAUTHORIZED);
+    };
+    if auth.agent_type != "dpi_probe" {
+        warn!("AUTH REJECT: agent {} ({}) is not a DPI probe", auth.agent_id, auth.agent_type);
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(auth)
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Result<&'a str, StatusCode> {
+    headers.get(name).and_then(|v| v.to_str().ok()).map(str::trim).ok_or_else(|| {
+        warn!("Rejected pcap upload: missing or invalid {} header", name);
+        StatusCode::BAD_REQUEST
+    })
+}
+
+fn micros_key(at: DateTime<Utc>, id: Uuid) -> String {
+    format!("{}|{}", at.to_rfc3339_opts(SecondsFormat::Micros, true), id)
+}
+
+/// POST /probes/captures/claim (Bearer, DPI probe): take the oldest pending capture request.
+#[utoipa::path(
+    post,
+    path = "/probes/captures/claim",
+    tag = "probes",
+    responses(
+        (status = 200, description = "Capture to run now", body = CaptureOrder),
+        (status = 204, description = "No capture requested, or the pcap_capture feature flag is off for this probe"),
+        (status = 401, description = "Missing or invalid agent token"),
+        (status = 403, description = "Agent is not a DPI probe"),
+        (status = 503, description = "Postgres control plane not configured"),
+    ),
+    security(("agent_token" = []))
+)]
+pub async fn handle_claim_capture(
+    State(state): State<AppState>,
+    auth: Option<Extension<AuthenticatedAgent>>,
+) -> Result<Response, StatusCode> {
+    let auth = probe(auth)?;
+    let db = control_db(&state)?;
+    pcap_capture::expire_stale(db).await.map_err(db_err("Failed to expire stale packet captures"))?;
+    if !state.feature_flags.is_enabled(Flag::PcapCapture, auth.tenant_id.as_deref()) {
+        return Ok(StatusCode::NO_CONTENT.into_response());
+    }
+    // SKIP LOCKED: concurrent probes never receive the same request
+    let row = db
+        .query_opt(
+            r#"
+            UPDATE pcap_captures
+            SET status = 'capturing', probe_agent_id = $1, claimed_at = now()
+            WHERE capture_id = (
+                SELECT capture_id FROM pcap_captures
+                WHERE status = 'requested'
+                ORDER BY requested_at, capture_id
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING capture_id, bpf_filter, duration_secs, max_bytes, snaplen, host_ip::text
+            "#,
+            &[&auth.agent_id],
+        )
+        .await
+        .map_err(db_err("Failed to claim packet capture"))?;
+    let Some(row) = row else {
+        return Ok(StatusCode::NO_CONTENT.into_response());
+    };
+    let order = CaptureOrder {
+        capture_id: row.get(0),
+        bpf_filter: row.get(1),
+        duration_secs: row.get(2),
+        max_bytes: row.get(3),
+        snaplen: row.get(4),
+    };
+    let host_ip: String = row.get(5);
+
+    http_agent_auth::audit(
+        state.store.as_ref(),
+        Some(auth.agent_id),
+        "PCAP_CAPTURE_CLAIMED",
+        Some(order.capture_id),
+        &serde_json::json!({
+            "capture_id": order.capture_id.to_string(),
+            "probe_agent_id": auth.agent_id.to_string(),
+            "host_ip": host_ip,
+            "bpf_filter": order.bpf_filter,
+            "duration_secs": order.duration_secs,
+            "max_bytes": order.max_bytes,
+        }),
+    )
+    .await?;
+    info!(
+        "Packet capture claimed | capture_id={} | probe={} | filter='{}' | duration_secs={}",
+        order.capture_id, auth.component_identity, order.bpf_filter, order.duration_secs
+    );
+    Ok(Json(order).into_response())
+}
+
+/// POST /probes/captures/upload (Bearer, DPI probe): the pcap of a claimed capture, named by
+/// X-Capture-Id with its SHA-256 in X-Content-SHA256. The file is spooled for the evidence store.
+#[utoipa::path(
+    post,
+    path = "/probes/captures/upload",
+    tag = "probes",
+    request_body(content = Vec<u8>, content_type = "application/vnd.tcpdump.pcap"),
+    params(
+        ("X-Capture-Id" = Uuid, Header, description = "capture_id of the claimed capture"),
+        ("X-Content-SHA256" = String, Header, description = "Hex SHA-256 of the body"),
+    ),
+    responses(
+        (status = 200, description = "pcap stored", body = CaptureUploadResponse),
+        (status = 400, description = "Missing headers, SHA-256 mismatch or not a pcap"),
+        (status = 401, description = "Missing or invalid agent token"),
+        (status = 403, description = "Agent is not a DPI probe"),
+        (status = 404, description = "No capture in progress with this id for this probe"),
+        (status = 413, description = "pcap larger than the capture's max_bytes"),
+        (status = 503, description = "Triggered captures off or postgres control plane not configured"),
+        (status = 507, description = "pcap spool over its disk quota; retry after Retry-After seconds"),
+    ),
+    security(("agent_token" = []))
+)]
+pub async fn handle_upload_capture(
+    State(state): State<AppState>,
+    auth: Option<Extension<AuthenticatedAgent>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<CaptureUploadResponse>, StatusCode> {
+    let auth = probe(auth)?;
+    let config = capture_config(&state)?;
+    let db = control_db(&state)?;
+    let capture_id = Uuid::parse_str(header(&headers, CAPTURE_ID_HEADER)?).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let claimed_sha256 = header(&headers, CONTENT_SHA256_HEADER)?.to_ascii_lowercase();
+
+    let row = db
+        .query_opt(
+            r#"
+            SELECT detection_id, incident_id, host_ip::text, bpf_filter, max_bytes, requested_at, claimed_at
+            FROM pcap_captures
+            WHERE capture_id = $1 AND probe_agent_id = $2 AND status = 'capturing'
+              AND claimed_at >= now() - make_interval(secs => duration_secs + $3)
+            "#,
+            &[&capture_id, &auth.agent_id, &(pcap_capture::UPLOAD_GRACE_SECS as f64)],
+        )
+        .await
+        .map_err(db_err("Failed to look up packet capture"))?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let max_bytes: i64 = row.get(4);
+    if body.len() as u64 > max_bytes.max(0) as u64 {
+        warn!("Rejected pcap upload {}: {} bytes exceed max_bytes {}", capture_id, body.len(), max_bytes);
+        return Err(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+    let pcap_sha256 = hex::encode(Sha256::digest(&body));
+    if pcap_sha256 != claimed_sha256 {
+        warn!("Rejected pcap upload {}: SHA-256 {} does not match X-Content-SHA256 {}", capture_id, pcap_sha256, claimed_sha256);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    pcap_capture::check_pcap(&body, max_bytes as u64).map_err(|e| {
+        warn!("Rejected pcap upload {}: {}", capture_id, e);
+        StatusCode::BAD_REQUEST
+    })?;
+    state.disk_quotas.admit(disk_quota::PCAP_SPOOL, body.len() as u64)?;
+
+    let manifest = SpoolManifest {
+        capture_id,
+        detection_id: row.get(0),
+        incident_id: row.get(1),
+        host_ip: row.get(2),
+        bpf_filter: row.get(3),
+        probe_agent_id: auth.agent_id,
+        probe_component_id: auth.component_identity.clone(),
+        requested_at: row.get(5),
+        claimed_at: row.get(6),
+        uploaded_at: Utc::now(),
+        pcap_file: format!("{}.pcap", capture_id),
+        pcap_sha256: pcap_sha256.clone(),
+        pcap_bytes: body.len() as u64,
+    };
+    // Spool first: the row only says uploaded once the file is on disk
+    let spool_dir = config.spool_dir.clone();
+    let spooled = {
+        let (spool_dir, manifest, body) = (spool_dir.clone(), manifest.clone(), body.clone());
+        tokio::task::spawn_blocking(move || pcap_capture::write_spool(&spool_dir, &manifest, &body)).await
+    };
+    match spooled {
+        Ok(Ok(())) => state.disk_quotas.record_write(disk_quota::PCAP_SPOOL, body.len() as u64),
+        Ok(Err(e)) => {
+            error!("FAIL-CLOSED: Failed to spool pcap {} in {}: {}", capture_id, spool_dir.display(), e);
+            pcap_capture::remove_spool(&spool_dir, capture_id);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+        Err(e) => {
+            error!("FAIL-CLOSED: pcap spool task failed for {}: {}", capture_id, e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+
+    let updated = db
+        .execute(
+            r#"
+            UPDATE pcap_captures
+            SET status = 'uploaded', finished_at = $3, pcap_sha256 = $4, pcap_bytes = $5
+            WHERE capture_id = $1 AND probe_agent_id = $2 AND status = 'capturing'
+            "#,
+            &[&capture_id, &auth.agent_id, &manifest.uploaded_at, &hex::decode(&pcap_sha256).unwrap_or_default(), &(body.len() as i64)],
+        )
+        .await;
+    match updated {
+        Ok(1) => {}
+        Ok(_) => {
+            // Expired or failed meanwhile: nothing may reach the evidence store without its row
+            warn!("Rejected pcap upload {}: capture is no longer in progress", capture_id);
+            pcap_capture::remove_spool(&spool_dir, capture_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => {
+            pcap_capture::remove_spool(&spool_dir, capture_id);
+            return Err(db_err("Failed to record pcap upload")(e));
+        }
+    }
+
+    http_agent_auth::audit(
+        state.store.as_ref(),
+        Some(auth.agent_id),
+        "PCAP_CAPTURE_UPLOADED",
+        Some(capture_id),
+        &serde_json::json!({
+            "capture_id": capture_id.to_string(),
+            "detection_id": manifest.detection_id.map(|id| id.to_string()),
+            "incident_id": manifest.incident_id.map(|id| id.to_string()),
+            "host_ip": manifest.host_ip,
+            "probe_agent_id": auth.agent_id.to_string(),
+            "pcap_sha256": pcap_sha256,
+            "pcap_bytes": manifest.pcap_bytes,
+        }),
+    )
+    .await?;
+    info!(
+        "Packet capture uploaded | capture_id={} | probe={} | bytes={} | sha256={}",
+        capture_id, auth.component_identity, manifest.pcap_bytes, pcap_sha256
+    );
+    Ok(Json(CaptureUploadResponse { capture_id: capture_id.to_string(), pcap_sha256, pcap_bytes: manifest.pcap_bytes }))
+}
+
+/// POST /probes/captures/fail (Bearer, DPI probe): a claimed capture could not be taken.
+#[utoipa::path(
+    post,
+    path = "/probes/captures/fail",
+    tag = "probes",
+    request_body = CaptureFailRequest,
+    responses(
+        (status = 200, description = "Capture marked failed", body = CaptureFailResponse),
+        (status = 400, description = "Empty or overlong reason"),
+        (status = 401, description = "Missing or invalid agent token"),
+        (status = 403, description = "Agent is not a DPI probe"),
+        (status = 404, description = "No capture in progress with this id for this probe"),
+        (status = 503, description = "Postgres control plane not configured"),
+    ),
+    security(("agent_token" = []))
+)]
+pub async fn handle_fail_capture(
+    State(state): State<AppState>,
+    auth: Option<Extension<AuthenticatedAgent>>,
+    Json(req): Json<CaptureFailRequest>,
+) -> Result<Json<CaptureFailResponse>, StatusCode> {
+    let auth = probe(auth)?;
+    if req.reason.trim().is_empty() || req.reason.len() > MAX_FAILURE_REASON_BYTES {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let updated = control_db(&state)?
+        .execute(
+            r#"
+            UPDATE pcap_captures
+            SET status = 'failed', finished_at = now(), failure_reason = $3
+            WHERE capture_id = $1 AND probe_agent_id = $2 AND status = 'capturing'
+            "#,
+            &[&req.capture_id, &auth.agent_id, &req.reason],
+        )
+        .await
+        .map_err(db_err("Failed to record packet capture failure"))?;
+    if updated == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    http_agent_auth::audit(
+        state.store.as_ref(),
+        Some(auth.agent_id),
+        "PCAP_CAPTURE_FAILED",
+        Some(req.capture_id),
+        &serde_json::json!({
+            "capture_id": req.capture_id.to_string(),
+            "probe_agent_id": auth.agent_id.to_string(),
+            "reason": req.reason,
+        }),
+    )
+    .await?;
+    warn!(
+        "Packet capture failed | capture_id={} | probe={} | reason={}",
+        req.capture_id, auth.component_identity, req.reason
+    );
+    Ok(Json(CaptureFailResponse { updated }))
+}
+
+/// GET /admin/pcap-captures (X-Admin-Key): capture requests and their outcome, oldest first, as
+/// a list page; filter[incident_id]=<id> selects the captures of an incident.
+#[utoipa::path(
+    get,
+    path = "/admin/pcap-captures",
+    tag = "admin",
+    params(
+        ("limit" = Option<usize>, Query, description = "Page size (1-500, default 50)"),
+        ("cursor" = Option<String>, Query, description = "next_cursor of the previous page"),
+        ("fields" = Option<String>, Query, description = "Comma-separated sparse fieldset"),
+    ),
+    responses(
+        (status = 200, description = "Page of capture requests (PcapCapture items)", body = Page),
+        (status = 400, description = "Unsupported list parameter"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_list_captures(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<ListQuery, ListQueryRejection>,
+) -> Result<Json<Page>, Response> {
+    state.admin_key.check(&headers).map_err(IntoResponse::into_response)?;
+    let query = query.map_err(IntoResponse::into_response)?;
+    let db = control_db(&state).map_err(IntoResponse::into_response)?;
+    let rows = db
+        .query(
+            r#"
+            SELECT capture_id, detection_id, incident_id, host_ip::text, bpf_filter, duration_secs, max_bytes,
+                   snaplen, status, requested_at, probe_agent_id, claimed_at, finished_at, pcap_sha256,
+                   pcap_bytes, failure_reason
+            FROM pcap_captures
+            "#,
+            &[],
+        )
+        .await
+        .map_err(db_err("Failed to list packet captures"))
+        .map_err(IntoResponse::into_response)?;
+    let captures: Vec<PcapCapture> = rows
+        .iter()
+        .map(|r| PcapCapture {
+            capture_id: r.get(0),
+            detection_id: r.get(1),
+            incident_id: r.get(2),
+            host_ip: r.get(3),
+            bpf_filter: r.get(4),
+            duration_secs: r.get(5),
+            max_bytes: r.get(6),
+            snaplen: r.get(7),
+            status: r.get(8),
+            requested_at: r.get(9),
+            probe_agent_id: r.get(10),
+            claimed_at: r.get(11),
+            finished_at: r.get(12),
+            pcap_sha256: r.get::<_, Option<Vec<u8>>>(13).map(hex::encode),
+            pcap_bytes: r.get(14),
+            failure_reason: r.get(15),
+        })
+        .collect();
+    query
+        .paginate(&CAPTURE_LIST, captures, |c| micros_key(c.requested_at, c.capture_id))
+        .map(Json)
+        .map_err(IntoResponse::into_response)
+}
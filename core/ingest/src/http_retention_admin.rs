@@ -0,0 +1,134 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/http_retention_admin.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Authenticated admin endpoints for retention run history - list recent retention_runs and the schedule health with failed, overdue and never-run alerts
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use chrono::Utc;
+use serde::Deserialize;
+use tokio_postgres::Client;
+use tracing::{error, warn};
+use utoipa::IntoParams;
+
+use crate::http_list::{ListQuery, ListQueryRejection, ListSpec, Page};
+use crate::http_server::AppState;
+use crate::retention_runs::{self, RetentionStatus};
+
+/// Most recent runs considered by the list (newest first); two weeks of hourly live and dry runs
+const RUN_HISTORY_WINDOW: i64 = 1_000;
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct RetentionStatusQuery {
+    /// Hours after the last successful live run before the schedule is overdue (default 3)
+    pub max_age_hours: Option<i64>,
+}
+
+const RUN_LIST: ListSpec = ListSpec {
+    filterable: &["mode", "status", "started_at", "rows_deleted", "lock_timeouts", "audit_id"],
+    selectable: &[
+        "run_id",
+        "mode",
+        "status",
+        "started_at",
+        "ended_at",
+        "duration_ms",
+        "tables",
+        "rows_would_purge",
+        "rows_deleted",
+        "held_rows_skipped",
+        "referenced_rows_skipped",
+        "orphan_rows_deleted",
+        "orphan_rows_marked",
+        "lock_timeouts",
+        "audit_id",
+        "error",
+        "table_stats",
+    ],
+};
+
+fn control_db(state: &AppState) -> Result<&Arc<Client>, StatusCode> {
+    state.db.as_ref().ok_or_else(|| {
+        error!("Retention run history requested but storage backend has no control plane (postgres required)");
+        StatusCode::SERVICE_UNAVAILABLE
+    })
+}
+
+fn read_err(e: String) -> StatusCode {
+    error!("FAIL-CLOSED: {}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+}
+
+/// GET /admin/retention/runs (X-Admin-Key): recent retention runs, live and dry, as a list page
+/// newest first.
+#[utoipa::path(
+    get,
+    path = "/admin/retention/runs",
+    tag = "admin",
+    params(
+        ("limit" = Option<usize>, Query, description = "Page size (1-500, default 50)"),
+        ("cursor" = Option<String>, Query, description = "next_cursor of the previous page"),
+        ("fields" = Option<String>, Query, description = "Comma-separated sparse fieldset"),
+    ),
+    responses(
+        (status = 200, description = "Page of runs (RetentionRun items)", body = Page),
+        (status = 400, description = "Unsupported list parameter"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_list_runs(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<ListQuery, ListQueryRejection>,
+) -> Result<Json<Page>, Response> {
+    state.admin_key.check(&headers).map_err(IntoResponse::into_response)?;
+    let query = query.map_err(IntoResponse::into_response)?;
+    let db = control_db(&state).map_err(IntoResponse::into_response)?;
+    let runs = retention_runs::recent(db, RUN_HISTORY_WINDOW)
+        .await
+        .map_err(read_err)
+        .map_err(IntoResponse::into_response)?;
+    // Newest first: invert the timestamp so ascending key order is descending time
+    query
+        .paginate(&RUN_LIST, runs, |r| format!("{:020}|{}", i64::MAX - r.started_at.timestamp_micros(), r.run_id))
+        .map(Json)
+        .map_err(IntoResponse::into_response)
+}
+
+/// GET /admin/retention/status (X-Admin-Key): whether live retention runs keep succeeding on
+/// schedule, with the alerts raised when they do not.
+#[utoipa::path(
+    get,
+    path = "/admin/retention/status",
+    tag = "admin",
+    params(RetentionStatusQuery),
+    responses(
+        (status = 200, description = "Retention schedule health", body = RetentionStatus),
+        (status = 400, description = "max_age_hours out of range"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_retention_status(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<RetentionStatusQuery>,
+) -> Result<Json<RetentionStatus>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    let max_age_hours = retention_runs::check_max_age_hours(
+        query.max_age_hours.unwrap_or(retention_runs::DEFAULT_MAX_AGE_HOURS),
+    )
+    .map_err(|e| {
+        warn!("Rejected retention status request: {}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+    let (live, last_success) = retention_runs::live_since_last_success(control_db(&state)?).await.map_err(read_err)?;
+    Ok(Json(retention_runs::assess(&live, last_success, max_age_hours, Utc::now())))
+}
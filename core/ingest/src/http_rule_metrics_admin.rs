@@ -0,0 +1,274 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/http_rule_metrics_admin.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Authenticated admin endpoints for detection rule metrics - close incidents with a true/false positive disposition (audited), list rule_metrics and the rule-tuning report of noisy and dead rules
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio_postgres::error::SqlState;
+use tokio_postgres::{Client, Row};
+use tracing::{error, info, warn};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::http_agent_auth;
+use crate::http_list::{ListQuery, ListQueryRejection, ListSpec, Page};
+use crate::http_server::AppState;
+use crate::rule_metrics::{self, Disposition, RuleMetrics, RuleTuningReport, TuningThresholds};
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CloseIncidentRequest {
+    pub incident_id: Uuid,
+    pub disposition: Disposition,
+    pub closed_by: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CloseIncidentResponse {
+    pub incident_id: Uuid,
+    pub disposition: Disposition,
+    /// Detections naming the incident when it was closed
+    pub detections: i64,
+    pub closed_at: DateTime<Utc>,
+}
+
+/// Overrides of the default tuning thresholds.
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct RuleTuningQuery {
+    /// Fewest firings in the window before a rule can be noisy (default 20)
+    pub noisy_min_firings: Option<i64>,
+    /// False positive rate at which a rule is noisy (default 0.5)
+    pub noisy_false_positive_rate: Option<f64>,
+    /// Suppression rate at which a rule is noisy (default 0.5)
+    pub noisy_suppression_rate: Option<f64>,
+    /// Days without a firing after which a rule is dead (default 30)
+    pub dead_after_days: Option<i64>,
+}
+
+const RULE_LIST: ListSpec = ListSpec {
+    filterable: &[
+        "detection_engine",
+        "detection_name",
+        "first_fired_at",
+        "last_fired_at",
+        "firings",
+        "suppressed",
+        "true_positives",
+        "false_positives",
+        "triaged",
+    ],
+    selectable: &[
+        "detection_engine",
+        "detection_name",
+        "first_fired_at",
+        "last_fired_at",
+        "window_start",
+        "firings",
+        "suppressed",
+        "true_positives",
+        "false_positives",
+        "triaged",
+        "median_triage_secs",
+        "refreshed_at",
+    ],
+};
+
+fn control_db(state: &AppState) -> Result<&Arc<Client>, StatusCode> {
+    state.db.as_ref().ok_or_else(|| {
+        error!("Rule metrics operation requested but storage backend has no control plane (postgres required)");
+        StatusCode::SERVICE_UNAVAILABLE
+    })
+}
+
+fn db_err(what: &str) -> impl FnOnce(tokio_postgres::Error) -> StatusCode + '_ {
+    move |e| {
+        error!("FAIL-CLOSED: {}: {}", what, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+fn bad_request(reason: String) -> StatusCode {
+    warn!("Rejected rule metrics request: {}", reason);
+    StatusCode::BAD_REQUEST
+}
+
+async fn load_rules(db: &Client) -> Result<Vec<RuleMetrics>, StatusCode> {
+    let rows = db
+        .query(
+            r#"
+            SELECT detection_engine, detection_name, first_fired_at, last_fired_at, window_start,
+                   firings, suppressed, true_positives, false_positives, triaged,
+                   median_triage_secs, refreshed_at
+            FROM rule_metrics
+            "#,
+            &[],
+        )
+        .await
+        .map_err(db_err("Failed to read rule metrics"))?;
+    Ok(rows.iter().map(rule_from_row).collect())
+}
+
+fn rule_from_row(r: &Row) -> RuleMetrics {
+    RuleMetrics {
+        detection_engine: r.get(0),
+        detection_name: r.get(1),
+        first_fired_at: r.get(2),
+        last_fired_at: r.get(3),
+        window_start: r.get(4),
+        firings: r.get(5),
+        suppressed: r.get(6),
+        true_positives: r.get(7),
+        false_positives: r.get(8),
+        triaged: r.get(9),
+        median_triage_secs: r.get(10),
+        refreshed_at: r.get(11),
+    }
+}
+
+/// POST /admin/incidents/close (X-Admin-Key): record the analyst disposition of an incident.
+/// Its detections count as true or false positives of their rules from the next refresh.
+#[utoipa::path(
+    post,
+    path = "/admin/incidents/close",
+    tag = "admin",
+    request_body = CloseIncidentRequest,
+    responses(
+        (status = 200, description = "Incident closed", body = CloseIncidentResponse),
+        (status = 400, description = "Empty or oversized closed_by or reason"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 404, description = "No detection names the incident"),
+        (status = 409, description = "The incident is already closed"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_close_incident(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<CloseIncidentRequest>,
+) -> Result<Json<CloseIncidentResponse>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    rule_metrics::check_closure(&req.closed_by, &req.reason).map_err(bad_request)?;
+    let db = control_db(&state)?;
+    let closed = db
+        .query_opt(
+            r#"
+            WITH named AS (
+                SELECT count(*) AS detections FROM detection_results
+                WHERE lower(artifacts->>'incident_id') = $1::text
+            )
+            INSERT INTO incident_closures (incident_id, disposition, closed_by, reason)
+            SELECT $2, $3, $4, $5 FROM named WHERE detections > 0
+            RETURNING (SELECT detections FROM named), closed_at
+            "#,
+            &[&req.incident_id.to_string(), &req.incident_id, &req.disposition.as_str(), &req.closed_by, &req.reason],
+        )
+        .await;
+    let row = match closed {
+        Ok(Some(row)) => row,
+        Ok(None) => return Err(StatusCode::NOT_FOUND),
+        Err(e) if e.code() == Some(&SqlState::UNIQUE_VIOLATION) => {
+            warn!("Rejected incident close: {} is already closed", req.incident_id);
+            return Err(StatusCode::CONFLICT);
+        }
+        Err(e) => return Err(db_err("Failed to close incident")(e)),
+    };
+    let detections: i64 = row.get(0);
+    let closed_at: DateTime<Utc> = row.get(1);
+
+    http_agent_auth::audit(
+        state.store.as_ref(),
+        None,
+        "INCIDENT_CLOSED",
+        Some(req.incident_id),
+        &serde_json::json!({
+            "incident_id": req.incident_id.to_string(),
+            "disposition": req.disposition.as_str(),
+            "closed_by": req.closed_by,
+            "reason": req.reason,
+            "detections": detections,
+        }),
+    )
+    .await?;
+    info!(
+        "Incident closed | incident_id={} | disposition={} | detections={} | by={}",
+        req.incident_id,
+        req.disposition.as_str(),
+        detections,
+        req.closed_by
+    );
+    Ok(Json(CloseIncidentResponse { incident_id: req.incident_id, disposition: req.disposition, detections, closed_at }))
+}
+
+/// GET /admin/rules/metrics (X-Admin-Key): per rule counts of the last refresh, as a list page
+/// ordered by engine and rule name.
+#[utoipa::path(
+    get,
+    path = "/admin/rules/metrics",
+    tag = "admin",
+    params(
+        ("limit" = Option<usize>, Query, description = "Page size (1-500, default 50)"),
+        ("cursor" = Option<String>, Query, description = "next_cursor of the previous page"),
+        ("fields" = Option<String>, Query, description = "Comma-separated sparse fieldset"),
+    ),
+    responses(
+        (status = 200, description = "Page of rules (RuleMetrics items)", body = Page),
+        (status = 400, description = "Unsupported list parameter"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_list_rule_metrics(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<ListQuery, ListQueryRejection>,
+) -> Result<Json<Page>, Response> {
+    state.admin_key.check(&headers).map_err(IntoResponse::into_response)?;
+    let query = query.map_err(IntoResponse::into_response)?;
+    let db = control_db(&state).map_err(IntoResponse::into_response)?;
+    let rules = load_rules(db).await.map_err(IntoResponse::into_response)?;
+    query
+        .paginate(&RULE_LIST, rules, |r| format!("{}|{}", r.detection_engine, r.detection_name))
+        .map(Json)
+        .map_err(IntoResponse::into_response)
+}
+
+/// GET /admin/rules/tuning (X-Admin-Key): noisy and dead rules under the given thresholds.
+#[utoipa::path(
+    get,
+    path = "/admin/rules/tuning",
+    tag = "admin",
+    params(RuleTuningQuery),
+    responses(
+        (status = 200, description = "Rule-tuning report", body = RuleTuningReport),
+        (status = 400, description = "Threshold out of range"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_rule_tuning(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<RuleTuningQuery>,
+) -> Result<Json<RuleTuningReport>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    let defaults = TuningThresholds::default();
+    let thresholds = TuningThresholds {
+        noisy_min_firings: query.noisy_min_firings.unwrap_or(defaults.noisy_min_firings),
+        noisy_false_positive_rate: query.noisy_false_positive_rate.unwrap_or(defaults.noisy_false_positive_rate),
+        noisy_suppression_rate: query.noisy_suppression_rate.unwrap_or(defaults.noisy_suppression_rate),
+        dead_after_days: query.dead_after_days.unwrap_or(defaults.dead_after_days),
+    };
+    thresholds.validate().map_err(bad_request)?;
+    let rules = load_rules(control_db(&state)?).await?;
+    Ok(Json(rule_metrics::tuning_report(rules, thresholds, Utc::now())))
+}
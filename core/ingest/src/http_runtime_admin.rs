@@ -0,0 +1,293 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/http_runtime_admin.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Authenticated admin endpoint and SIGUSR1 path for runtime log filter / sampling overrides - audited, auto-reverted after TTL
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use serde::Deserialize;
+use utoipa::ToSchema;
+use crypto::digest::Sha256;
+use tracing::{error, info, warn};
+
+use crate::runtime_controls::{RuntimeChange, RuntimeControls, RuntimeState};
+use crate::load_shedding::LoadShedStats;
+use crate::pipeline::PipelineStats;
+use crate::service_health;
+use crate::service_heartbeat::OrchestratorLinkStatus;
+use health::HealthReport;
+use crate::storage::TelemetryStore;
+
+use crate::http_agent_auth;
+use crate::operator_auth;
+use crate::http_server::AppState;
+
+/// Operator key for /admin/* (X-Admin-Key). Admin routes answer 503 when unset, unless operator
+/// authentication is on: then they also accept requests the operator middleware authorized.
+pub struct AdminKey {
+    key: Option<Vec<u8>>,
+    /// GRANT_HEADER value set by operator_auth::authorize (None: operator authentication off)
+    operator_grant: Option<Vec<u8>>,
+}
+
+impl AdminKey {
+    pub fn new(key: Option<Vec<u8>>) -> Self {
+        Self { key, operator_grant: None }
+    }
+
+    pub fn from_env() -> Result<Self, String> {
+        match std::env::var("RANSOMEYE_ADMIN_KEY_PATH") {
+            Ok(path) => {
+                let raw = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("FAIL-CLOSED: cannot read admin key {}: {}", path, e))?;
+                let key = raw.trim().as_bytes().to_vec();
+                if key.len() < 16 {
+                    return Err(format!("FAIL-CLOSED: admin key {} must be at least 16 bytes", path));
+                }
+                Ok(Self::new(Some(key)))
+            }
+            Err(_) => Ok(Self::new(None)),
+        }
+    }
+
+    /// Accept requests carrying the operator middleware's grant (see operator_auth::GRANT_HEADER).
+    pub fn with_operator_grant(mut self, grant: Option<&str>) -> Self {
+        self.operator_grant = grant.map(|g| g.as_bytes().to_vec());
+        self
+    }
+
+    pub fn check(&self, headers: &HeaderMap) -> Result<(), StatusCode> {
+        if let Some(grant) = &self.operator_grant {
+            if let Some(presented) = headers.get(operator_auth::GRANT_HEADER) {
+                if crypto::constant_time_eq(presented.as_bytes(), grant) {
+                    return Ok(());
+                }
+                warn!("Rejected admin request with an invalid operator grant");
+                return Err(StatusCode::UNAUTHORIZED);
+            }
+        }
+        let Some(expected) = &self.key else {
+            if self.operator_grant.is_some() {
+                return Err(StatusCode::UNAUTHORIZED);
+            }
+            error!("Admin request received but RANSOMEYE_ADMIN_KEY_PATH is not configured");
+            return Err(StatusCode::SERVICE_UNAVAILABLE);
+        };
+        let provided = headers
+            .get("x-admin-key")
+            .and_then(|v| v.to_str().ok())
+            .ok_or(StatusCode::UNAUTHORIZED)?;
+        let a = Sha256::digest(expected);
+        let b = Sha256::digest(provided.trim().as_bytes());
+        if crypto::constant_time_eq(&a, &b) {
+            Ok(())
+        } else {
+            warn!("Rejected admin request with invalid admin key");
+            Err(StatusCode::UNAUTHORIZED)
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RuntimeConfigRequest {
+    pub log_filter: Option<String>,
+    pub sample_ratio: Option<f64>,
+    pub ttl_secs: Option<u64>,
+    /// Free-text operator justification recorded in the audit log.
+    pub reason: Option<String>,
+}
+
+/// GET /admin/runtime-config (X-Admin-Key)
+#[utoipa::path(
+    get,
+    path = "/admin/runtime-config",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Effective runtime settings", body = RuntimeState),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "RANSOMEYE_ADMIN_KEY_PATH not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_get_runtime_config(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<RuntimeState>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    Ok(Json(state.controls.state()))
+}
+
+/// GET /admin/orchestrator-link (X-Admin-Key): this instance's view of the orchestrator heartbeat.
+#[utoipa::path(
+    get,
+    path = "/admin/orchestrator-link",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Orchestrator link state (enabled=false without RANSOMEYE_ORCHESTRATOR_URL)", body = OrchestratorLinkStatus),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "RANSOMEYE_ADMIN_KEY_PATH not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_get_orchestrator_link(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<OrchestratorLinkStatus>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    Ok(Json(state.orchestrator_link.status(Instant::now())))
+}
+
+/// GET /admin/pipeline (X-Admin-Key): pipeline shards with their queue depth and counters.
+#[utoipa::path(
+    get,
+    path = "/admin/pipeline",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Shard count, queue bound, backlog lane settings and per-shard queued, processed and refused units of work", body = PipelineStats),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "RANSOMEYE_ADMIN_KEY_PATH not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_get_pipeline(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<PipelineStats>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    Ok(Json(state.pipeline.stats()))
+}
+
+/// GET /admin/load-shedding (X-Admin-Key): load-shedding mode, thresholds, state and counters.
+#[utoipa::path(
+    get,
+    path = "/admin/load-shedding",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Mode, thresholds, whether shedding now and events relaxed or refused since start", body = LoadShedStats),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "RANSOMEYE_ADMIN_KEY_PATH not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_get_load_shedding(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<LoadShedStats>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    Ok(Json(state.load_shedder.stats()))
+}
+
+/// GET /admin/health (X-Admin-Key): this instance's health report - drain, pipeline, load
+/// shedding, spool disk quotas and orchestrator link - as sent with every orchestrator heartbeat.
+#[utoipa::path(
+    get,
+    path = "/admin/health",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Overall status (worst subsystem), subsystem breakdown and metrics", body = HealthReport),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "RANSOMEYE_ADMIN_KEY_PATH not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_get_health(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<HealthReport>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    Ok(Json(service_health::report(
+        &state.drain,
+        &state.pipeline.stats(),
+        &state.load_shedder.stats(),
+        &state.disk_quotas,
+        &state.dashboard,
+        &state.orchestrator_link.status(Instant::now()),
+    )))
+}
+
+/// POST /admin/runtime-config (X-Admin-Key): apply a TTL-bounded override.
+#[utoipa::path(
+    post,
+    path = "/admin/runtime-config",
+    tag = "admin",
+    request_body = RuntimeConfigRequest,
+    responses(
+        (status = 200, description = "Override applied", body = RuntimeState),
+        (status = 400, description = "Invalid filter, ratio or TTL"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "RANSOMEYE_ADMIN_KEY_PATH not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_set_runtime_config(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<RuntimeConfigRequest>,
+) -> Result<Json<RuntimeState>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    let change = RuntimeChange {
+        log_filter: req.log_filter,
+        sample_ratio: req.sample_ratio,
+        ttl_secs: req.ttl_secs,
+    };
+    apply_override(&state.store, &state.controls, &change, "admin_api", req.reason.as_deref()).await
+}
+
+/// Apply + audit + schedule revert. Shared by the admin endpoint and the SIGUSR1 handler.
+pub async fn apply_override(
+    store: &Arc<dyn TelemetryStore>,
+    controls: &Arc<RuntimeControls>,
+    change: &RuntimeChange,
+    trigger: &str,
+    reason: Option<&str>,
+) -> Result<Json<RuntimeState>, StatusCode> {
+    let previous = controls.state();
+    let applied = controls.apply(change).map_err(|e| {
+        warn!("Runtime config override rejected: {}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+
+    let payload = serde_json::json!({
+        "trigger": trigger,
+        "reason": reason,
+        "previous": previous,
+        "applied": applied,
+    });
+    // Audit failure does not roll back the override (the revert timer still bounds it).
+    if let Err(code) = http_agent_auth::audit(store.as_ref(), None, "RUNTIME_CONFIG_OVERRIDE", None, &payload).await {
+        error!("Runtime config override applied but audit write failed ({})", code);
+    }
+    info!(
+        "Runtime config override | trigger={} | log_filter={} | sample_ratio={} | ttl_secs={:?}",
+        trigger, applied.log_filter, applied.sample_ratio, applied.ttl_secs
+    );
+
+    schedule_revert(store.clone(), controls.clone(), applied.generation, applied.ttl_secs.unwrap_or(0));
+    Ok(Json(applied))
+}
+
+fn schedule_revert(store: Arc<dyn TelemetryStore>, controls: Arc<RuntimeControls>, generation: u64, ttl_secs: u64) {
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_secs(ttl_secs)).await;
+        match controls.revert_if(generation) {
+            Ok(Some(restored)) => {
+                info!("Runtime config override expired; restored baseline | log_filter={}", restored.log_filter);
+                let payload = serde_json::json!({
+                    "trigger": "ttl_expired",
+                    "reverted_generation": generation,
+                    "restored": restored,
+                });
+                if let Err(code) = http_agent_auth::audit(store.as_ref(), None, "RUNTIME_CONFIG_REVERT", None, &payload).await {
+                    error!("Runtime config revert audit write failed ({})", code);
+                }
+            }
+            // Superseded by a newer override; that override owns the revert.
+            Ok(None) => {}
+            Err(e) => error!("Runtime config revert failed: {}", e),
+        }
+    });
+}
@@ -0,0 +1,404 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/http_sandbox.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Sandbox submission endpoints - agents submit suspicious binaries by hash or sample (agent bearer token, policy-gated), operators submit hashes and list submissions with their verdicts (admin key, audited)
+
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+    Extension,
+};
+use axum::body::Bytes;
+use chrono::{DateTime, SecondsFormat, Utc};
+use crypto::digest::Sha256;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::error::SqlState;
+use tokio_postgres::Client;
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::disk_quota;
+use crate::feature_flags::{self, Flag};
+use crate::http_agent_auth::{self, AuthenticatedAgent};
+use crate::http_list::{ListQuery, ListQueryRejection, ListSpec, Page};
+use crate::http_server::AppState;
+use crate::sandbox::{self, NewSubmission, SandboxConfig, SandboxSubmission, SubmitMode, MAX_TEXT_BYTES, SUBMISSION_COLUMNS};
+
+/// Sample header with the hex SHA-256 of the file (required, also without a body)
+pub const CONTENT_SHA256_HEADER: &str = "x-content-sha256";
+/// Sample header with the path the agent found the file at
+pub const FILE_PATH_HEADER: &str = "x-file-path";
+/// Sample header with the file size (hash-only submissions)
+pub const FILE_SIZE_HEADER: &str = "x-file-size";
+/// Agent types that may submit samples
+const SUBMITTING_AGENTS: &[&str] = &["linux_agent", "windows_agent"];
+
+/// POST /admin/sandbox-submissions body: a hash to look up in the sandbox.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SandboxSubmitRequest {
+    /// Hex SHA-256 of the file
+    pub sha256: String,
+    pub file_path: Option<String>,
+    /// Agent the file was seen on (optional)
+    pub agent_id: Option<Uuid>,
+    pub incident_id: Option<Uuid>,
+    /// Case or incident reference
+    pub reason: String,
+    pub requested_by: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SandboxSubmitResponse {
+    pub submission_id: String,
+    /// hash or sample
+    pub mode: String,
+    /// queued, submitted, reported or not_found
+    pub status: String,
+    /// clean, suspicious or malicious once reported
+    pub verdict: Option<String>,
+    /// false: the file was already submitted; this is that submission
+    pub created: bool,
+}
+
+impl From<(SandboxSubmission, bool)> for SandboxSubmitResponse {
+    fn from((s, created): (SandboxSubmission, bool)) -> Self {
+        Self { submission_id: s.submission_id.to_string(), mode: s.mode, status: s.status, verdict: s.verdict, created }
+    }
+}
+
+const SUBMISSION_LIST: ListSpec = ListSpec {
+    filterable: &["sha256", "agent_id", "incident_id", "source", "mode", "status", "verdict", "queued_at"],
+    selectable: &[
+        "submission_id",
+        "sha256",
+        "file_path",
+        "file_size",
+        "agent_id",
+        "incident_id",
+        "source",
+        "submitted_by",
+        "reason",
+        "mode",
+        "status",
+        "queued_at",
+        "submitted_at",
+        "finished_at",
+        "sandbox_task_id",
+        "score",
+        "verdict",
+        "report_sha256",
+        "detection_id",
+        "failure_reason",
+    ],
+};
+
+fn control_db(state: &AppState) -> Result<&Arc<Client>, StatusCode> {
+    state.db.as_ref().ok_or_else(|| {
+        error!("Sandbox operation requested but storage backend has no control plane (postgres required)");
+        StatusCode::SERVICE_UNAVAILABLE
+    })
+}
+
+fn sandbox_config(state: &AppState) -> Result<&SandboxConfig, StatusCode> {
+    state.sandbox.as_deref().ok_or_else(|| {
+        warn!("Sandbox operation requested but no sandbox is configured (RANSOMEYE_INGEST_SANDBOX_URL)");
+        StatusCode::SERVICE_UNAVAILABLE
+    })
+}
+
+fn db_err(what: &str) -> impl FnOnce(tokio_postgres::Error) -> StatusCode + '_ {
+    move |e| {
+        error!("FAIL-CLOSED: {}: {}", what, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+/// Samples come from token-authenticated host agents only.
+fn host_agent(auth: Option<Extension<AuthenticatedAgent>>) -> Result<AuthenticatedAgent, StatusCode> {
+    let Some(Extension(auth)) = auth else {
+        warn!("AUTH REJECT: sandbox submission requires an agent token");
+        return Err(StatusCode::UNAUTHORIZED);
+    };
+    if !SUBMITTING_AGENTS.contains(&auth.agent_type.as_str()) {
+        warn!("AUTH REJECT: agent {} ({}) may not submit samples", auth.agent_id, auth.agent_type);
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(auth)
+}
+
+fn optional_header<'a>(headers: &'a HeaderMap, name: &str) -> Result<Option<&'a str>, StatusCode> {
+    match headers.get(name) {
+        None => Ok(None),
+        Some(v) => v.to_str().map(|s| Some(s.trim())).map_err(|_| {
+            warn!("Rejected sandbox sample: invalid {} header", name);
+            StatusCode::BAD_REQUEST
+        }),
+    }
+}
+
+fn text_ok(s: &str) -> bool {
+    !s.trim().is_empty() && s.len() <= MAX_TEXT_BYTES
+}
+
+fn micros_key(at: DateTime<Utc>, id: Uuid) -> String {
+    format!("{}|{}", at.to_rfc3339_opts(SecondsFormat::Micros, true), id)
+}
+
+/// POST /agents/sandbox/samples (Bearer, Linux or Windows agent): submit a suspicious binary.
+/// X-Content-SHA256 names it; the file itself is the body when policy allows samples, else the
+/// body is empty and only the hash is looked up.
+#[utoipa::path(
+    post,
+    path = "/agents/sandbox/samples",
+    tag = "agents",
+    request_body(content = Vec<u8>, content_type = "application/octet-stream"),
+    params(
+        ("X-Content-SHA256" = String, Header, description = "Hex SHA-256 of the file"),
+        ("X-File-Path" = Option<String>, Header, description = "Where the agent found the file"),
+        ("X-File-Size" = Option<i64>, Header, description = "File size (hash-only submissions)"),
+    ),
+    responses(
+        (status = 200, description = "Submission recorded, or the existing one for this file", body = SandboxSubmitResponse),
+        (status = 400, description = "Missing or invalid headers, or SHA-256 mismatch"),
+        (status = 401, description = "Missing or invalid agent token"),
+        (status = 403, description = "Agent may not submit, or policy allows hashes only"),
+        (status = 409, description = "Concurrent submission of the same file; retry"),
+        (status = 413, description = "Sample larger than the policy allows"),
+        (status = 503, description = "No sandbox or postgres control plane configured, or sandbox_detonation feature flag off for this agent"),
+        (status = 507, description = "Sandbox spool over its disk quota; retry after Retry-After seconds"),
+    ),
+    security(("agent_token" = []))
+)]
+pub async fn handle_submit_sample(
+    State(state): State<AppState>,
+    auth: Option<Extension<AuthenticatedAgent>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<SandboxSubmitResponse>, StatusCode> {
+    let auth = host_agent(auth)?;
+    let config = sandbox_config(&state)?;
+    let db = control_db(&state)?;
+    if !state.feature_flags.is_enabled(Flag::SandboxDetonation, auth.tenant_id.as_deref()) {
+        warn!("Refused sandbox submission from {}: feature flag {} is off", auth.agent_id, Flag::SandboxDetonation.as_str());
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+    let sha256 = optional_header(&headers, CONTENT_SHA256_HEADER)?.unwrap_or_default().to_ascii_lowercase();
+    if !sandbox::is_sha256_hex(&sha256) {
+        warn!("Rejected sandbox sample from {}: missing or invalid {} header", auth.agent_id, CONTENT_SHA256_HEADER);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let file_path = optional_header(&headers, FILE_PATH_HEADER)?.map(str::to_string);
+    if file_path.as_deref().is_some_and(|p| !text_ok(p)) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let mode = if body.is_empty() { SubmitMode::Hash } else { SubmitMode::Sample };
+    let file_size = match mode {
+        SubmitMode::Sample => Some(body.len() as i64),
+        SubmitMode::Hash => optional_header(&headers, FILE_SIZE_HEADER)?
+            .map(|s| s.parse::<i64>().ok().filter(|n| *n >= 0).ok_or(StatusCode::BAD_REQUEST))
+            .transpose()?,
+    };
+
+    if mode == SubmitMode::Sample {
+        if config.submit != SubmitMode::Sample {
+            warn!("Refused sandbox sample {} from {}: policy allows hashes only", sha256, auth.agent_id);
+            return Err(StatusCode::FORBIDDEN);
+        }
+        if body.len() as u64 > config.max_sample_bytes {
+            warn!("Refused sandbox sample {}: {} bytes exceed {}", sha256, body.len(), config.max_sample_bytes);
+            return Err(StatusCode::PAYLOAD_TOO_LARGE);
+        }
+        let actual = hex::encode(Sha256::digest(&body));
+        if actual != sha256 {
+            warn!("Rejected sandbox sample from {}: SHA-256 {} does not match {}", auth.agent_id, actual, sha256);
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        state.disk_quotas.admit(disk_quota::SANDBOX_SPOOL, body.len() as u64)?;
+        // Spool first: a queued sample submission always has its file
+        let spooled = {
+            let (spool_dir, sha256, body) = (config.spool_dir.clone(), sha256.clone(), body.clone());
+            tokio::task::spawn_blocking(move || sandbox::write_sample(&spool_dir, &sha256, &body)).await
+        };
+        if !matches!(spooled, Ok(Ok(()))) {
+            error!("FAIL-CLOSED: Failed to spool sandbox sample {} in {}", sha256, config.spool_dir.display());
+            sandbox::remove_sample(&config.spool_dir, &sha256);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+        state.disk_quotas.record_write(disk_quota::SANDBOX_SPOOL, body.len() as u64);
+    }
+
+    let new = NewSubmission {
+        sha256: sha256.clone(),
+        file_path,
+        file_size,
+        agent_id: Some(auth.agent_id),
+        incident_id: None,
+        source: "agent",
+        submitted_by: auth.component_identity.clone(),
+        reason: None,
+        mode,
+    };
+    let submitted = sandbox::submit(db, &new).await;
+    // The spooled sample is only kept for a queued sample submission
+    let keep_sample = matches!(&submitted, Ok(Some((s, _))) if s.status == "queued" && s.mode == "sample");
+    if mode == SubmitMode::Sample && !keep_sample {
+        sandbox::remove_sample(&config.spool_dir, &sha256);
+    }
+    let (submission, created) = submitted.map_err(db_err("Failed to record sandbox submission"))?.ok_or_else(|| {
+        warn!("Sandbox submission of {} raced a concurrent one", sha256);
+        StatusCode::CONFLICT
+    })?;
+
+    if created {
+        http_agent_auth::audit(
+            state.store.as_ref(),
+            Some(auth.agent_id),
+            "SANDBOX_SUBMITTED",
+            Some(submission.submission_id),
+            &serde_json::json!({
+                "submission_id": submission.submission_id.to_string(),
+                "sha256": submission.sha256,
+                "file_path": submission.file_path,
+                "file_size": submission.file_size,
+                "agent_id": auth.agent_id.to_string(),
+                "mode": submission.mode,
+            }),
+        )
+        .await?;
+        info!(
+            "Sandbox submission | submission_id={} | sha256={} | mode={} | agent={}",
+            submission.submission_id, submission.sha256, submission.mode, auth.component_identity
+        );
+    }
+    Ok(Json((submission, created).into()))
+}
+
+/// POST /admin/sandbox-submissions (X-Admin-Key): look up a hash in the sandbox.
+#[utoipa::path(
+    post,
+    path = "/admin/sandbox-submissions",
+    tag = "admin",
+    request_body = SandboxSubmitRequest,
+    responses(
+        (status = 200, description = "Submission recorded, or the existing one for this file", body = SandboxSubmitResponse),
+        (status = 400, description = "Invalid SHA-256, empty or overlong text"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 404, description = "Unknown agent"),
+        (status = 409, description = "Concurrent submission of the same file; retry"),
+        (status = 503, description = "No sandbox, admin key or postgres control plane configured, or sandbox_detonation feature flag off"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_submit_hash(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<SandboxSubmitRequest>,
+) -> Result<Json<SandboxSubmitResponse>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    sandbox_config(&state)?;
+    let db = control_db(&state)?;
+    let sha256 = req.sha256.trim().to_ascii_lowercase();
+    if !sandbox::is_sha256_hex(&sha256)
+        || !text_ok(&req.reason)
+        || !text_ok(&req.requested_by)
+        || req.file_path.as_deref().is_some_and(|p| !text_ok(p))
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    // Without an agent the global value applies
+    let tenant = match req.agent_id {
+        Some(agent_id) => feature_flags::agent_tenant(db, agent_id).await.map_err(db_err("Failed to look up agent"))?,
+        None => None,
+    };
+    if !state.feature_flags.is_enabled(Flag::SandboxDetonation, tenant.as_deref()) {
+        warn!("Refused sandbox submission of {}: feature flag {} is off", sha256, Flag::SandboxDetonation.as_str());
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+    let new = NewSubmission {
+        sha256,
+        file_path: req.file_path.clone(),
+        file_size: None,
+        agent_id: req.agent_id,
+        incident_id: req.incident_id,
+        source: "operator",
+        submitted_by: req.requested_by.clone(),
+        reason: Some(req.reason.clone()),
+        mode: SubmitMode::Hash,
+    };
+    let (submission, created) = match sandbox::submit(db, &new).await {
+        Ok(Some(submitted)) => submitted,
+        Ok(None) => return Err(StatusCode::CONFLICT),
+        Err(e) if e.code() == Some(&SqlState::FOREIGN_KEY_VIOLATION) => {
+            warn!("Refused sandbox submission: unknown agent {:?}", req.agent_id);
+            return Err(StatusCode::NOT_FOUND);
+        }
+        Err(e) => return Err(db_err("Failed to record sandbox submission")(e)),
+    };
+
+    if created {
+        http_agent_auth::audit(
+            state.store.as_ref(),
+            None,
+            "SANDBOX_SUBMITTED",
+            Some(submission.submission_id),
+            &serde_json::json!({
+                "submission_id": submission.submission_id.to_string(),
+                "sha256": submission.sha256,
+                "file_path": submission.file_path,
+                "agent_id": submission.agent_id.map(|id| id.to_string()),
+                "incident_id": submission.incident_id.map(|id| id.to_string()),
+                "mode": submission.mode,
+                "reason": req.reason,
+                "requested_by": req.requested_by,
+            }),
+        )
+        .await?;
+        info!(
+            "Sandbox submission | submission_id={} | sha256={} | mode=hash | by={}",
+            submission.submission_id, submission.sha256, req.requested_by
+        );
+    }
+    Ok(Json((submission, created).into()))
+}
+
+/// GET /admin/sandbox-submissions (X-Admin-Key): submissions and their verdicts, oldest first, as
+/// a list page; filter[verdict]=malicious selects the detonations that raised a detection.
+#[utoipa::path(
+    get,
+    path = "/admin/sandbox-submissions",
+    tag = "admin",
+    params(
+        ("limit" = Option<usize>, Query, description = "Page size (1-500, default 50)"),
+        ("cursor" = Option<String>, Query, description = "next_cursor of the previous page"),
+        ("fields" = Option<String>, Query, description = "Comma-separated sparse fieldset"),
+    ),
+    responses(
+        (status = 200, description = "Page of sandbox submissions (SandboxSubmission items)", body = Page),
+        (status = 400, description = "Unsupported list parameter"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_list_submissions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<ListQuery, ListQueryRejection>,
+) -> Result<Json<Page>, Response> {
+    state.admin_key.check(&headers).map_err(IntoResponse::into_response)?;
+    let query = query.map_err(IntoResponse::into_response)?;
+    let db = control_db(&state).map_err(IntoResponse::into_response)?;
+    let rows = db
+        .query(&format!("SELECT {} FROM sandbox_submissions", SUBMISSION_COLUMNS), &[])
+        .await
+        .map_err(db_err("Failed to list sandbox submissions"))
+        .map_err(IntoResponse::into_response)?;
+    let submissions: Vec<SandboxSubmission> = rows.iter().map(sandbox::submission_from_row).collect();
+    query
+        .paginate(&SUBMISSION_LIST, submissions, |s| micros_key(s.queued_at, s.submission_id))
+        .map(Json)
+        .map_err(IntoResponse::into_response)
+}
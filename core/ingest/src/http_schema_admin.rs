@@ -0,0 +1,245 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/http_schema_admin.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Authenticated live schema compatibility probe - contract version, applied migrations, latest validation status and per-table size estimates for capacity planning
+
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio_postgres::error::SqlState;
+use tokio_postgres::Client;
+use tracing::error;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::http_server::AppState;
+
+/// Migrations returned, newest first.
+const MIGRATION_HISTORY_LIMIT: i64 = 100;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SchemaMigration {
+    pub migration_id: Uuid,
+    pub contract_sha256: String,
+    /// full (first run) or incremental (missing required tables only)
+    pub kind: String,
+    pub tables_applied: Vec<String>,
+    pub applied_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SchemaValidation {
+    pub contract_sha256: String,
+    /// passed or failed
+    pub status: String,
+    pub detail: Option<String>,
+    pub validated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TableSize {
+    pub table: String,
+    /// Planner statistics (pg_stat_user_tables.n_live_tup), not an exact COUNT(*)
+    pub estimated_rows: i64,
+    /// Heap, indexes and TOAST
+    pub total_bytes: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SchemaStatus {
+    /// SHA-256 of the authoritative schema file last validated against (schema.hash); None before the first recorded validation
+    pub contract_sha256: Option<String>,
+    /// passed, failed, or unknown when no validation has been recorded
+    pub validation_status: String,
+    pub last_validation: Option<SchemaValidation>,
+    /// Newest first
+    pub migrations: Vec<SchemaMigration>,
+    /// Every table in schema ransomeye, by name
+    pub tables: Vec<TableSize>,
+    pub total_bytes: i64,
+    /// Schema generation this ingest build was written for (schema_compat::SCHEMA_VERSION)
+    pub code_schema_version: i32,
+    /// Current row of schema_versions; None before the orchestrator recorded one
+    pub schema_generation: Option<i32>,
+    /// Dual-written renames not yet retired, as table.old_column->new_column
+    pub renamed_columns: Vec<String>,
+    pub generated_at: DateTime<Utc>,
+}
+
+fn control_db(state: &AppState) -> Result<&Arc<Client>, StatusCode> {
+    state.db.as_ref().ok_or_else(|| {
+        error!("Schema probe requested but storage backend has no control plane (postgres required)");
+        StatusCode::SERVICE_UNAVAILABLE
+    })
+}
+
+fn db_err(what: &str) -> impl FnOnce(tokio_postgres::Error) -> StatusCode + '_ {
+    move |e| {
+        error!("FAIL-CLOSED: {}: {}", what, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+/// Provenance tables are absent until the orchestrator has applied them; report "nothing recorded" then.
+fn undefined_table_as_empty<T: Default>(
+    what: &'static str,
+) -> impl FnOnce(tokio_postgres::Error) -> Result<T, StatusCode> {
+    move |e| {
+        if e.code() == Some(&SqlState::UNDEFINED_TABLE) {
+            Ok(T::default())
+        } else {
+            Err(db_err(what)(e))
+        }
+    }
+}
+
+/// GET /schema (X-Admin-Key): live schema compatibility probe.
+#[utoipa::path(
+    get,
+    path = "/schema",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Contract version, migrations, validation status, schema generation and table sizes", body = SchemaStatus),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_get_schema(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<SchemaStatus>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    let db = control_db(&state)?;
+    Ok(Json(load_schema_status(db).await?))
+}
+
+/// Read the probe from the control plane. Unqualified provenance tables resolve through the
+/// connection's search_path; table sizes always come from schema ransomeye.
+pub async fn load_schema_status(db: &Client) -> Result<SchemaStatus, StatusCode> {
+    let migrations: Vec<SchemaMigration> = match db
+        .query(
+            r#"
+            SELECT migration_id, contract_sha256, kind, tables_applied, applied_at
+            FROM schema_migrations
+            ORDER BY applied_at DESC
+            LIMIT $1
+            "#,
+            &[&MIGRATION_HISTORY_LIMIT],
+        )
+        .await
+    {
+        Ok(rows) => rows
+            .iter()
+            .map(|r| SchemaMigration {
+                migration_id: r.get(0),
+                contract_sha256: r.get(1),
+                kind: r.get(2),
+                tables_applied: r.get(3),
+                applied_at: r.get(4),
+            })
+            .collect(),
+        Err(e) => undefined_table_as_empty("Failed to read schema migrations")(e)?,
+    };
+
+    let last_validation: Option<SchemaValidation> = match db
+        .query_opt(
+            r#"
+            SELECT contract_sha256, status, detail, validated_at
+            FROM schema_validations
+            ORDER BY validated_at DESC
+            LIMIT 1
+            "#,
+            &[],
+        )
+        .await
+    {
+        Ok(row) => row.map(|r| SchemaValidation {
+            contract_sha256: r.get(0),
+            status: r.get(1),
+            detail: r.get(2),
+            validated_at: r.get(3),
+        }),
+        Err(e) => undefined_table_as_empty("Failed to read schema validations")(e)?,
+    };
+
+    let schema_generation: Option<i32> = match db
+        .query_opt("SELECT max(schema_version) FROM schema_versions", &[])
+        .await
+    {
+        Ok(row) => row.and_then(|r| r.get(0)),
+        Err(e) => undefined_table_as_empty("Failed to read schema versions")(e)?,
+    };
+
+    let renamed_columns: Vec<String> = match db
+        .query(
+            r#"
+            SELECT table_name, old_column, new_column
+            FROM schema_column_renames
+            WHERE retired_at IS NULL
+            ORDER BY table_name, old_column
+            "#,
+            &[],
+        )
+        .await
+    {
+        Ok(rows) => rows
+            .iter()
+            .map(|r| format!("{}.{}->{}", r.get::<_, String>(0), r.get::<_, String>(1), r.get::<_, String>(2)))
+            .collect(),
+        Err(e) => undefined_table_as_empty("Failed to read column renames")(e)?,
+    };
+
+    let tables: Vec<TableSize> = db
+        .query(
+            r#"
+            SELECT relname::text, n_live_tup, pg_total_relation_size(relid)
+            FROM pg_stat_user_tables
+            WHERE schemaname = 'ransomeye'
+            ORDER BY relname
+            "#,
+            &[],
+        )
+        .await
+        .map_err(db_err("Failed to read table statistics"))?
+        .iter()
+        .map(|r| TableSize { table: r.get(0), estimated_rows: r.get(1), total_bytes: r.get(2) })
+        .collect();
+
+    Ok(schema_status(last_validation, migrations, tables, schema_generation, renamed_columns))
+}
+
+/// Assemble the probe response. The contract version is the one last validated against, else the
+/// one of the newest migration (migrations newest first); the status is unknown until a validation
+/// has been recorded.
+pub fn schema_status(
+    last_validation: Option<SchemaValidation>,
+    migrations: Vec<SchemaMigration>,
+    tables: Vec<TableSize>,
+    schema_generation: Option<i32>,
+    renamed_columns: Vec<String>,
+) -> SchemaStatus {
+    SchemaStatus {
+        contract_sha256: last_validation
+            .as_ref()
+            .map(|v| v.contract_sha256.clone())
+            .or_else(|| migrations.first().map(|m| m.contract_sha256.clone())),
+        validation_status: last_validation
+            .as_ref()
+            .map(|v| v.status.clone())
+            .unwrap_or_else(|| "unknown".to_string()),
+        last_validation,
+        migrations,
+        total_bytes: tables.iter().map(|t| t.total_bytes).sum(),
+        tables,
+        code_schema_version: crate::schema_compat::SCHEMA_VERSION,
+        schema_generation,
+        renamed_columns,
+        generated_at: Utc::now(),
+    }
+}
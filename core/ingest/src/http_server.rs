@@ -1,1038 +1,2432 @@
 // Path and File Name : /home/ransomeye/rebuild/core/ingest/src/http_server.rs
 // Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
-// Details of functionality of this file: HTTP ingestion server with POST /ingest/linux and /ingest/dpi endpoints - verifies signatures and writes to database
+// Details of functionality of this file: HTTP ingestion server with POST /ingest/linux and /ingest/dpi endpoints - verifies signatures and persists through the configured telemetry store
 
+use std::future::IntoFuture;
 use std::sync::Arc;
-use std::net::IpAddr;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Instant;
 use axum::{
-    extract::State,
+    body::Bytes,
+    extract::{ConnectInfo, DefaultBodyLimit, FromRef, State},
     http::StatusCode,
+    middleware,
     response::Json,
-    routing::post,
-    Router,
+    routing::{get, post},
+    Extension, Router,
 };
-use serde::{Deserialize, Serialize};
+use serde::Serialize;
+use utoipa::ToSchema;
+use serde_json::value::RawValue;
 use serde_json::Value as JsonValue;
-use tokio_postgres::{Client, NoTls};
-use tracing::{info, error, warn};
+use tokio_postgres::Client;
+use tracing::{info, warn, error, info_span, Instrument};
 use uuid::Uuid;
-use sha2::{Sha256, Digest};
+use crypto::digest::Sha256;
 use base64::{Engine as _, engine::general_purpose};
 use chrono::{DateTime, Utc};
 use hostname;
-use ring::rand::{SecureRandom, SystemRandom};
 use hex;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SignedEvent {
-    pub envelope: JsonValue,  // EventEnvelope as JSON
-    pub payload_hash: String,  // SHA-256 hex of canonical envelope JSON bytes
-    pub signature: String,     // Base64 signature of payload_hash
-    pub signer_id: String,     // Key identifier
-}
+use crate::agent_cache::AgentIdentityCache;
+use crate::dashboard_cache::DashboardCache;
+use crate::component_budget::{BudgetDecision, BudgetViolation, ComponentQuota, ComponentRateBudget};
+use crate::drop_accounting::{self, DropAccountingConfig, IngestDropLedger};
+use crate::envelope_validation::{self, IngestRejection, ValidationCategory, ValidationError};
+use crate::handoff::{self, DrainController, HandoffRecord, ListenerConfig};
+use crate::host_inventory;
+use crate::export::{ExportConfig, ExportLimiter};
+use crate::feature_flags::FeatureFlags;
+use crate::identity_conflict::{ConflictMode, IdentityConflicts, IdentityDecision};
+use crate::key_pinning::{KeyMismatch, KeyPinMode, KeyPinning, PinDecision};
+use crate::lineage::{LineageEvent, LineageFinding, LineageVerifier};
+use crate::load_shedding::{self, Admission, LoadShedConfig, LoadShedMode, LoadShedder, ShedPriority};
+use crate::outbox::{self, OutboxConfig, OutboxRelay, TcpPublisher};
+use crate::payload_policy::{self, PayloadFacts, PayloadStoragePolicy};
+use crate::agent_log::AgentLogConfig;
+use crate::memory_acquisition::MemoryAcquisitionPolicy;
+use crate::pcap_capture::PcapCaptureConfig;
+use crate::disk_quota::{self, DiskQuotaConfig, DiskQuotas};
+use crate::pipeline::{PipelineConfig, PipelineError, ShardedPipeline};
+use crate::sandbox::{SandboxConfig, SandboxWorker};
+use crate::protocol::dpi_mapping::DpiFieldMappings;
+use crate::protocol::signed_event::{EnvelopeRef, LinuxEventData, LinuxLineage, SignedEventRef};
+use crate::residency::{ResidencyDecision, ResidencyPolicy};
+use crate::runtime_controls::RuntimeControls;
+use crate::signature_verification::{EventSignatureVerifier, SignatureMode};
+use crate::shared_state::{self, PostgresRateWindows, SharedStateConfig, SharedStateMode};
+use crate::service_health;
+use crate::service_heartbeat::{self, HeartbeatClient, HeartbeatConfig, OrchestratorLink, ServiceStatus};
+use crate::storage::postgres::{self, PostgresStore};
+use crate::suppression::SuppressionSigners;
+use crate::topology::{CrossZoneMovement, Endpoint, TopologyState};
+use crate::yara_rules::YaraSigners;
+use crate::audit_review::AuditReviewers;
+use crate::rule_metrics::{self, RuleMetricsConfig};
+use crate::storage::{
+    self, AuditRecord, DetectionRecord, DpiTelemetry, IdentityConflictRecord, IdentityOrigin, LinuxTelemetry, PayloadStorage,
+    QuarantinedEventRecord, RawEventRecord, SignatureValidationRecord, StorageBackend, StorageConfig, StorageError, StorageTx,
+    TelemetryProvenance, TelemetryRecord, TelemetrySource, TelemetryStore,
+};
 
-#[derive(Debug, Serialize)]
+use crate::http_agent_auth::{self, AgentTokenAuthority, AuthenticatedAgent, IngestAuthMode};
+use crate::http_annotation_admin;
+use crate::http_audit_review_admin;
+use crate::http_dashboard_admin;
+use crate::http_drops_admin;
+use crate::http_export_admin;
+use crate::http_feature_flags_admin;
+use crate::http_fleet_admin;
+use crate::http_identity_admin;
+use crate::http_legal_hold_admin;
+use crate::http_retention_admin;
+use crate::http_rule_metrics_admin;
+use crate::http_agent_log;
+use crate::http_memory_acquisition;
+use crate::http_operator_auth;
+use crate::http_pcap_capture;
+use crate::http_sandbox;
+use crate::http_runtime_admin::{self, AdminKey};
+use crate::http_schema_admin;
+use crate::http_suppression_admin;
+use crate::http_maintenance_admin;
+use crate::http_webhook_admin;
+use crate::http_yara;
+use crate::openapi;
+use crate::operator_auth::{self, OperatorAuth, OperatorGate};
+
+#[derive(Debug, Serialize, ToSchema)]
 pub struct IngestResponse {
     pub status: String,
     pub message_id: String,
 }
 
 pub struct HttpIngestionServer {
-    db_client: Arc<Client>,
+    store: Arc<dyn TelemetryStore>,
+    db_client: Option<Arc<Client>>,
+    tokens: Arc<AgentTokenAuthority>,
+    controls: Arc<RuntimeControls>,
+    admin_key: Arc<AdminKey>,
+    operator_auth: Arc<OperatorAuth>,
+    payload_policy: Arc<PayloadStoragePolicy>,
+    budget: Arc<ComponentRateBudget>,
+    key_pins: Arc<KeyPinning>,
+    signatures: Arc<EventSignatureVerifier>,
+    identity_conflicts: Arc<IdentityConflicts>,
+    residency: Arc<ResidencyPolicy>,
+    topology: Arc<TopologyState>,
+    lineage: Arc<LineageVerifier>,
+    agent_cache: Arc<AgentIdentityCache>,
+    dashboard: Arc<DashboardCache>,
+    outbox: Arc<OutboxConfig>,
+    dpi_mapping: Arc<DpiFieldMappings>,
+    orchestrator_link: Arc<OrchestratorLink>,
+    heartbeat: Option<HeartbeatConfig>,
+    listener_cfg: ListenerConfig,
+    drain: Arc<DrainController>,
+    drops: Arc<IngestDropLedger>,
+    drop_cfg: DropAccountingConfig,
+    suppression_signers: Arc<SuppressionSigners>,
+    pcap_capture: Option<Arc<PcapCaptureConfig>>,
+    memory_acquisition: Option<Arc<MemoryAcquisitionPolicy>>,
+    agent_logs: Option<Arc<AgentLogConfig>>,
+    sandbox: Option<Arc<SandboxConfig>>,
+    disk_quotas: Arc<DiskQuotas>,
+    yara_signers: Arc<YaraSigners>,
+    audit_reviewers: Arc<AuditReviewers>,
+    /// Post-verification persistence sharded by agent id; workers start in `start`
+    pipeline: Arc<ShardedPipeline>,
+    load_shedder: Arc<LoadShedder>,
+    export: Arc<ExportLimiter>,
+    feature_flags: Arc<FeatureFlags>,
+    shared_state: SharedStateConfig,
+    rule_metrics: RuleMetricsConfig,
     listen_addr: String,
+    max_body_bytes: usize,
+    /// Serve /openapi.json and /swagger-ui (RANSOMEYE_INGEST_OPENAPI)
+    openapi: bool,
+}
+
+/// Upper bound on a single ingest request body (RANSOMEYE_INGEST_MAX_BODY_BYTES); bodies are parsed in memory.
+const DEFAULT_MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// Shared router state. Ingest handlers extract `State<Arc<dyn TelemetryStore>>`; `db` is the
+/// Postgres control plane (agent tokens) and is `None` on the SQLite lab backend.
+#[derive(Clone)]
+pub struct AppState {
+    pub store: Arc<dyn TelemetryStore>,
+    pub db: Option<Arc<Client>>,
+    pub tokens: Arc<AgentTokenAuthority>,
+    pub controls: Arc<RuntimeControls>,
+    pub admin_key: Arc<AdminKey>,
+    /// OIDC and break-glass operators on the admin API (disabled: admin key only)
+    pub operator_auth: Arc<OperatorAuth>,
+    pub payload_policy: Arc<PayloadStoragePolicy>,
+    pub budget: Arc<ComponentRateBudget>,
+    pub key_pins: Arc<KeyPinning>,
+    /// Ed25519 event signature verification against registered agent keys
+    pub signatures: Arc<EventSignatureVerifier>,
+    pub identity_conflicts: Arc<IdentityConflicts>,
+    pub residency: Arc<ResidencyPolicy>,
+    /// Sites, zones and sensor placement (no topology: events are not labelled)
+    pub topology: Arc<TopologyState>,
+    pub lineage: Arc<LineageVerifier>,
+    pub agent_cache: Arc<AgentIdentityCache>,
+    /// Dashboard aggregates, invalidated by the detections and enrollments of this instance
+    pub dashboard: Arc<DashboardCache>,
+    pub outbox: Arc<OutboxConfig>,
+    pub dpi_mapping: Arc<DpiFieldMappings>,
+    pub orchestrator_link: Arc<OrchestratorLink>,
+    pub drops: Arc<IngestDropLedger>,
+    pub suppression_signers: Arc<SuppressionSigners>,
+    /// Triggered packet captures of hosts with critical detections (None: off)
+    pub pcap_capture: Option<Arc<PcapCaptureConfig>>,
+    /// Operator-requested agent memory acquisition policy (None: off)
+    pub memory_acquisition: Option<Arc<MemoryAcquisitionPolicy>>,
+    /// Sealed agent log segments for the evidence store (None: off)
+    pub agent_logs: Option<Arc<AgentLogConfig>>,
+    /// Sandbox detonation of suspicious binaries (None: off)
+    pub sandbox: Option<Arc<SandboxConfig>>,
+    /// Budgets of the evidence spools above (none configured: uploads are not limited)
+    pub disk_quotas: Arc<DiskQuotas>,
+    /// Keys trusted to sign YARA rule packs (none: new packs are refused)
+    pub yara_signers: Arc<YaraSigners>,
+    /// Who may attest to audit log reviews (none: attestations are refused)
+    pub audit_reviewers: Arc<AuditReviewers>,
+    /// Per-agent ordered persistence of accepted events
+    pub pipeline: Arc<ShardedPipeline>,
+    /// Sheds low-priority events and slow agents when the database cannot keep up
+    pub load_shedder: Arc<LoadShedder>,
+    /// Concurrency and per-minute limits of bulk data exports
+    pub export: Arc<ExportLimiter>,
+    /// Runtime switches for risky capabilities (global and per tenant)
+    pub feature_flags: Arc<FeatureFlags>,
+    /// Drain state and in-flight requests (GET /admin/health)
+    pub drain: Arc<DrainController>,
+}
+
+impl FromRef<AppState> for Arc<dyn TelemetryStore> {
+    fn from_ref(state: &AppState) -> Arc<dyn TelemetryStore> {
+        state.store.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<RuntimeControls> {
+    fn from_ref(state: &AppState) -> Arc<RuntimeControls> {
+        state.controls.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<PayloadStoragePolicy> {
+    fn from_ref(state: &AppState) -> Arc<PayloadStoragePolicy> {
+        state.payload_policy.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<ComponentRateBudget> {
+    fn from_ref(state: &AppState) -> Arc<ComponentRateBudget> {
+        state.budget.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<KeyPinning> {
+    fn from_ref(state: &AppState) -> Arc<KeyPinning> {
+        state.key_pins.clone()
+    }
+}
+
+/// The keys an event's signer may use: its pinned signer_id and the public key its signature
+/// must verify against. One extractor for both (ingest handlers are at axum's argument limit).
+#[derive(Clone)]
+pub struct SignerKeys {
+    pub pins: Arc<KeyPinning>,
+    pub signatures: Arc<EventSignatureVerifier>,
+}
+
+impl FromRef<AppState> for SignerKeys {
+    fn from_ref(state: &AppState) -> SignerKeys {
+        SignerKeys { pins: state.key_pins.clone(), signatures: state.signatures.clone() }
+    }
+}
+
+impl FromRef<AppState> for Arc<IdentityConflicts> {
+    fn from_ref(state: &AppState) -> Arc<IdentityConflicts> {
+        state.identity_conflicts.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<AgentIdentityCache> {
+    fn from_ref(state: &AppState) -> Arc<AgentIdentityCache> {
+        state.agent_cache.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<DashboardCache> {
+    fn from_ref(state: &AppState) -> Arc<DashboardCache> {
+        state.dashboard.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<OutboxConfig> {
+    fn from_ref(state: &AppState) -> Arc<OutboxConfig> {
+        state.outbox.clone()
+    }
+}
+
+/// Where an event's data may be held and where its sensor sits. One extractor for both, as the
+/// ingest handlers are at axum's argument limit.
+#[derive(Clone)]
+pub struct Placement {
+    pub residency: Arc<ResidencyPolicy>,
+    pub topology: Arc<TopologyState>,
+}
+
+impl FromRef<AppState> for Placement {
+    fn from_ref(state: &AppState) -> Placement {
+        Placement { residency: state.residency.clone(), topology: state.topology.clone() }
+    }
+}
+
+impl FromRef<AppState> for Arc<LineageVerifier> {
+    fn from_ref(state: &AppState) -> Arc<LineageVerifier> {
+        state.lineage.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<ShardedPipeline> {
+    fn from_ref(state: &AppState) -> Arc<ShardedPipeline> {
+        state.pipeline.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<LoadShedder> {
+    fn from_ref(state: &AppState) -> Arc<LoadShedder> {
+        state.load_shedder.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<DpiFieldMappings> {
+    fn from_ref(state: &AppState) -> Arc<DpiFieldMappings> {
+        state.dpi_mapping.clone()
+    }
 }
 
 impl HttpIngestionServer {
-    pub async fn new(listen_addr: String) -> Result<Self, Box<dyn std::error::Error>> {
-        // Load DB config from environment
-        let db_host = std::env::var("DB_HOST")
-            .unwrap_or_else(|_| "localhost".to_string());
-        let db_port = std::env::var("DB_PORT")
-            .unwrap_or_else(|_| "5432".to_string())
-            .parse::<u16>()
-            .map_err(|e| format!("Invalid DB_PORT: {}", e))?;
-        let db_name = std::env::var("DB_NAME")
-            .unwrap_or_else(|_| "ransomeye".to_string());
-        let db_user = std::env::var("DB_USER")
-            .unwrap_or_else(|_| "gagan".to_string());
-        let db_pass = std::env::var("DB_PASS")
-            .unwrap_or_else(|_| "gagan".to_string());
-
-        let connection_string = format!(
-            "host={} port={} dbname={} user={} password={}",
-            db_host, db_port, db_name, db_user, db_pass
-        );
+    pub async fn new(listen_addr: String, controls: Arc<RuntimeControls>) -> Result<Self, Box<dyn std::error::Error>> {
+        // Storage backend from RANSOMEYE_STORAGE_BACKEND (postgres default, sqlite for lab mode)
+        let storage_config = StorageConfig::from_env()?;
+        let (store, db_client) = storage::open_from_env(&storage_config).await?;
+        Self::with_store(listen_addr, controls, store, db_client)
+    }
+
+    /// Build on an already-open store (embedded in the orchestrator for lite mode).
+    /// `db_client` is the Postgres control plane; `None` means no agent token tables.
+    pub fn with_store(
+        listen_addr: String,
+        controls: Arc<RuntimeControls>,
+        store: Arc<dyn TelemetryStore>,
+        db_client: Option<Arc<Client>>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        // Per-agent bearer tokens (interim until mTLS) - FAIL-CLOSED on misconfiguration
+        let tokens = AgentTokenAuthority::from_env()?;
+        // OIDC / break-glass operators alongside the admin key - FAIL-CLOSED on partial configuration
+        let operator_auth = OperatorAuth::from_env()?;
+        let admin_key = AdminKey::from_env()?.with_operator_grant(operator_auth.grant());
+
+        // Token tables are Postgres-only; refuse to start token mode without them
+        if tokens.mode == IngestAuthMode::Token && db_client.is_none() {
+            return Err(format!(
+                "FAIL-CLOSED: agent token auth requires postgres backend (storage backend={}); set RANSOMEYE_INGEST_AUTH_MODE=disabled for lab mode",
+                store.backend().as_str()
+            )
+            .into());
+        }
+
+        // Signing key pins live in the Postgres control plane - FAIL-CLOSED like token mode
+        let key_pins = KeyPinning::from_env(db_client.clone())?;
+
+        // Agent public keys: trust store files, then agents.signing_public_key - FAIL-CLOSED with neither
+        let signatures = EventSignatureVerifier::from_env(db_client.clone())?;
+
+        let max_body_bytes = match std::env::var("RANSOMEYE_INGEST_MAX_BODY_BYTES") {
+            Ok(v) => v.parse::<usize>().ok().filter(|n| *n > 0)
+                .ok_or_else(|| format!("Invalid RANSOMEYE_INGEST_MAX_BODY_BYTES '{}'", v))?,
+            Err(_) => DEFAULT_MAX_BODY_BYTES,
+        };
+
+        // raw_events payload storage policy (full by default)
+        let payload_policy = PayloadStoragePolicy::from_env()?;
+
+        // Duplicate agent-id detection (quarantine by default); open conflicts are restored in `start`
+        let identity_conflicts = IdentityConflicts::from_env()?;
+
+        // Region pinning for multi-region deployments (unrestricted without RANSOMEYE_INGEST_REGION)
+        let residency = ResidencyPolicy::from_env()?;
+        match &residency.local_region {
+            Some(region) => info!(
+                "Data residency: region={} | allowed_routes={} | untagged={:?}",
+                region, residency.allowed_routes.len(), residency.untagged
+            ),
+            None => info!("Data residency: no RANSOMEYE_INGEST_REGION, cross-region delivery not enforced"),
+        }
+
+        // Signed site/zone topology - FAIL-CLOSED on a document that does not verify
+        let topology = TopologyState::from_env()?;
+        match topology.topology() {
+            Some(t) => info!(
+                "Topology: sites={} | zones={} | sensors={} | document_sha256={}",
+                t.site_count(), t.zone_count(), t.sensor_count(), t.document_sha256
+            ),
+            None => info!("Topology: no RANSOMEYE_TOPOLOGY_PATH, events carry no zone labels"),
+        }
+
+        // Per-boot process lineage chains of Linux agents (detections only, never a rejection)
+        let lineage = LineageVerifier::from_env()?;
+
+        // Identity -> agent_id resolution cache for unauthenticated ingest
+        let agent_cache = AgentIdentityCache::from_env()?;
+
+        // Dashboard aggregate queries, served from memory within their TTL
+        let dashboard = DashboardCache::from_env()?;
+
+        // Accepted-telemetry messages written in the ingest transaction, relayed after commit
+        let outbox = OutboxConfig::from_env()?;
+
+        // DPI envelope.data -> column mapping per envelope schema version (FAIL-CLOSED if an override is invalid)
+        let dpi_mapping = DpiFieldMappings::from_env()?;
+        info!("DPI field mapping versions: {:?}", dpi_mapping.versions());
+
+        let openapi = openapi::enabled_from_env();
+
+        // Registration/heartbeat to the orchestrator (disabled without RANSOMEYE_ORCHESTRATOR_URL)
+        let heartbeat = HeartbeatConfig::from_env()?;
+        let instance_id = hostname::get().unwrap_or_default().to_string_lossy().to_string();
+        let orchestrator_link = Arc::new(OrchestratorLink::new(
+            service_heartbeat::INGEST_SERVICE_NAME,
+            &instance_id,
+            heartbeat.as_ref(),
+        ));
+
+        // Socket activation / SO_REUSEPORT, drain timeout and the restart handoff file
+        let listener_cfg = ListenerConfig::from_env()?;
+
+        // Daily dropped-event accounting (agent reports and ingest-side refusals)
+        let drop_cfg = DropAccountingConfig::from_env()?;
 
-        let (client, connection) = tokio_postgres::connect(&connection_string, NoTls)
-            .await
-            .map_err(|e| format!("Database connection failed: {}", e))?;
+        // Keys trusted to sign detection suppression rules (none: new rules are refused)
+        let suppression_signers = SuppressionSigners::from_env()?;
+
+        // Keys trusted to sign YARA rule packs for Linux agents (none: new packs are refused)
+        let yara_signers = YaraSigners::from_env()?;
+
+        // Reviewers who may attest to audit log reviews (none: attestations are refused)
+        let audit_reviewers = AuditReviewers::from_env()?;
+
+        // Triggered packet captures: probes claim them over the control channel, pcaps are spooled
+        // for the evidence store - FAIL-CLOSED if the spool is unusable or there is no control plane
+        let pcap_capture = PcapCaptureConfig::from_env()?;
+        if let Some(cfg) = &pcap_capture {
+            if db_client.is_none() {
+                return Err("FAIL-CLOSED: RANSOMEYE_INGEST_PCAP_CAPTURE requires the postgres backend".into());
+            }
+            std::fs::create_dir_all(&cfg.spool_dir)
+                .map_err(|e| format!("FAIL-CLOSED: cannot create pcap spool {}: {}", cfg.spool_dir.display(), e))?;
+            info!(
+                "Triggered packet captures on | duration_secs={} | max_bytes={} | spool={}",
+                cfg.duration_secs, cfg.max_bytes, cfg.spool_dir.display()
+            );
+        }
+
+        // Operator-requested memory acquisition: agents stream images in hashed chunks to the spool
+        // - FAIL-CLOSED if the spool is unusable or there is no control plane
+        let memory_acquisition = MemoryAcquisitionPolicy::from_env()?;
+        if let Some(policy) = &memory_acquisition {
+            if db_client.is_none() {
+                return Err("FAIL-CLOSED: RANSOMEYE_INGEST_MEMORY_ACQUISITION requires the postgres backend".into());
+            }
+            std::fs::create_dir_all(&policy.spool_dir).map_err(|e| {
+                format!("FAIL-CLOSED: cannot create memory spool {}: {}", policy.spool_dir.display(), e)
+            })?;
+            let scopes: Vec<&str> = policy.scopes.iter().map(|s| s.as_str()).collect();
+            info!(
+                "Memory acquisition on | scopes={} | max_bytes={} | chunk_bytes={} | spool={}",
+                scopes.join(","), policy.max_bytes, policy.chunk_bytes, policy.spool_dir.display()
+            );
+        }
+
+        // Sealed agent log segments: verified and spooled for the evidence store - FAIL-CLOSED if the
+        // spool is unusable or there is no control plane
+        let agent_logs = AgentLogConfig::from_env()?;
+        if let Some(cfg) = &agent_logs {
+            if db_client.is_none() {
+                return Err("FAIL-CLOSED: RANSOMEYE_INGEST_AGENT_LOGS requires the postgres backend".into());
+            }
+            std::fs::create_dir_all(&cfg.spool_dir)
+                .map_err(|e| format!("FAIL-CLOSED: cannot create agent log spool {}: {}", cfg.spool_dir.display(), e))?;
+            info!(
+                "Sealed agent logs on | max_segment_bytes={} | spool={}",
+                cfg.max_segment_bytes, cfg.spool_dir.display()
+            );
+        }
+
+        // Sandbox detonation: submissions go out, verdicts come back and reports are spooled for
+        // the evidence store - FAIL-CLOSED if the spool is unusable or there is no control plane
+        let sandbox = SandboxConfig::from_env()?;
+        if let Some(cfg) = &sandbox {
+            if db_client.is_none() {
+                return Err("FAIL-CLOSED: RANSOMEYE_INGEST_SANDBOX_URL requires the postgres backend".into());
+            }
+            std::fs::create_dir_all(cfg.spool_dir.join(crate::sandbox::SAMPLES_DIR))
+                .map_err(|e| format!("FAIL-CLOSED: cannot create sandbox spool {}: {}", cfg.spool_dir.display(), e))?;
+            info!(
+                "Sandbox detonation on | sandbox={} | submit={} | malicious_score={} | spool={}",
+                cfg.api_url, cfg.submit.as_str(), cfg.malicious_score, cfg.spool_dir.display()
+            );
+        }
+
+        // Spool budgets: a quota on a spool that is off, or an invalid quota, is FAIL-CLOSED
+        let disk_quota_cfg = DiskQuotaConfig::from_env()?;
+        let disk_quotas = DiskQuotas::new(
+            &disk_quota_cfg,
+            &[
+                (disk_quota::PCAP_SPOOL, pcap_capture.as_ref().map(|c| c.spool_dir.as_path())),
+                (disk_quota::MEMORY_SPOOL, memory_acquisition.as_ref().map(|p| p.spool_dir.as_path())),
+                (disk_quota::SANDBOX_SPOOL, sandbox.as_ref().map(|c| c.spool_dir.as_path())),
+                (disk_quota::AGENT_LOG_SPOOL, agent_logs.as_ref().map(|c| c.spool_dir.as_path())),
+            ],
+        )?;
+        for spec in &disk_quota_cfg.quotas {
+            info!(
+                "Disk quota on {} | budget_mb={} | policy={} | warn_percent={} | scan_secs={}",
+                spec.name,
+                spec.budget_bytes / (1024 * 1024),
+                spec.policy.as_str(),
+                disk_quota_cfg.warn_percent,
+                disk_quota_cfg.scan_interval.as_secs()
+            );
+        }
 
-        tokio::spawn(async move {
-            if let Err(e) = connection.await {
-                error!("Database connection error: {}", e);
+        // Replay and rate-budget state shared by load-balanced instances - FAIL-CLOSED without a database
+        let shared_state = SharedStateConfig::from_env()?;
+        let budget = match (shared_state.mode, &db_client) {
+            (SharedStateMode::Local, _) => ComponentRateBudget::new(Vec::new()),
+            (SharedStateMode::Postgres, Some(db)) => {
+                ComponentRateBudget::new(Vec::new()).with_shared_windows(PostgresRateWindows::new(db.clone()))
             }
-        });
+            (SharedStateMode::Postgres, None) => {
+                return Err("FAIL-CLOSED: RANSOMEYE_INGEST_SHARED_STATE=postgres requires the postgres backend".into())
+            }
+        };
+        info!("Shared ingest state: {}", shared_state.mode.as_str());
+
+        // Per detection rule metrics for the rule-tuning report (refreshed only with postgres)
+        let rule_metrics = RuleMetricsConfig::from_env()?;
+        if db_client.is_some() {
+            info!(
+                "Rule metrics: refresh_secs={} | window_days={}",
+                rule_metrics.refresh_interval.as_secs(),
+                rule_metrics.window_days
+            );
+        }
+
+        // Accepted events are persisted by per-agent shards; SQLite has a single writer, so one shard
+        let mut pipeline_cfg = PipelineConfig::from_env()?;
+        if store.backend() == StorageBackend::Sqlite {
+            pipeline_cfg.shards = 1;
+        }
+        let pipeline = Arc::new(ShardedPipeline::new(pipeline_cfg));
+
+        // Load shedding when the database cannot keep up (off by default)
+        let load_shed = LoadShedConfig::from_env()?;
+        if load_shed.mode != LoadShedMode::Off {
+            info!(
+                "Load shedding: mode={} | latency_ms={} | queue_pct={} | retry_after_secs={}",
+                load_shed.mode.as_str(), load_shed.latency.as_millis(), load_shed.queue_pct, load_shed.retry_after_secs
+            );
+        }
 
-        // Set search_path
-        client
-            .batch_execute("SET search_path = ransomeye, public;")
-            .await
-            .map_err(|e| format!("Failed to set search_path: {}", e))?;
+        // Bulk NDJSON export of normalized events for external analytics
+        let export_cfg = ExportConfig::from_env()?;
+        info!(
+            "Data export: max_concurrent={} | requests_per_minute={}",
+            export_cfg.max_concurrent, export_cfg.requests_per_minute
+        );
 
-        info!("HTTP Ingestion Server initialized with DB connection");
+        info!("HTTP Ingestion Server initialized with {} storage backend", store.backend().as_str());
 
         Ok(Self {
-            db_client: Arc::new(client),
+            store,
+            db_client,
+            tokens: Arc::new(tokens),
+            controls,
+            admin_key: Arc::new(admin_key),
+            operator_auth: Arc::new(operator_auth),
+            payload_policy: Arc::new(payload_policy),
+            budget: Arc::new(budget),
+            shared_state,
+            rule_metrics,
+            key_pins: Arc::new(key_pins),
+            signatures: Arc::new(signatures),
+            identity_conflicts: Arc::new(identity_conflicts),
+            residency: Arc::new(residency),
+            topology: Arc::new(topology),
+            lineage: Arc::new(lineage),
+            agent_cache: Arc::new(agent_cache),
+            dashboard: Arc::new(dashboard),
+            outbox: Arc::new(outbox),
+            dpi_mapping: Arc::new(dpi_mapping),
+            orchestrator_link,
+            heartbeat,
+            listener_cfg,
+            drain: Arc::new(DrainController::new()),
+            drops: Arc::new(IngestDropLedger::new()),
+            drop_cfg,
+            suppression_signers: Arc::new(suppression_signers),
+            pcap_capture: pcap_capture.map(Arc::new),
+            memory_acquisition: memory_acquisition.map(Arc::new),
+            agent_logs: agent_logs.map(Arc::new),
+            sandbox: sandbox.map(Arc::new),
+            disk_quotas: Arc::new(disk_quotas),
+            yara_signers: Arc::new(yara_signers),
+            audit_reviewers: Arc::new(audit_reviewers),
+            pipeline,
+            load_shedder: Arc::new(LoadShedder::new(load_shed)),
+            export: Arc::new(ExportLimiter::new(export_cfg)),
+            feature_flags: Arc::new(FeatureFlags::new()),
             listen_addr,
+            max_body_bytes,
+            openapi,
         })
     }
 
-    pub async fn start(self) -> Result<(), Box<dyn std::error::Error>> {
-        let app = Router::new()
+    /// Start with these per-component quotas (lite mode: resolved in-process by the orchestrator).
+    /// With a Postgres control plane, `start` replaces them from ingest_component_quotas.
+    pub fn with_component_quotas(self, quotas: Vec<ComponentQuota>) -> Self {
+        self.budget.replace_quotas(quotas);
+        self
+    }
+
+    /// Drain control for signal handlers: stop accepting, finish in-flight requests, then return from `start`.
+    pub fn drain(&self) -> Arc<DrainController> {
+        self.drain.clone()
+    }
+
+    pub fn app_state(&self) -> AppState {
+        AppState {
+            store: self.store.clone(),
+            db: self.db_client.clone(),
+            tokens: self.tokens.clone(),
+            controls: self.controls.clone(),
+            admin_key: self.admin_key.clone(),
+            operator_auth: self.operator_auth.clone(),
+            payload_policy: self.payload_policy.clone(),
+            budget: self.budget.clone(),
+            key_pins: self.key_pins.clone(),
+            signatures: self.signatures.clone(),
+            identity_conflicts: self.identity_conflicts.clone(),
+            residency: self.residency.clone(),
+            topology: self.topology.clone(),
+            lineage: self.lineage.clone(),
+            agent_cache: self.agent_cache.clone(),
+            dashboard: self.dashboard.clone(),
+            outbox: self.outbox.clone(),
+            dpi_mapping: self.dpi_mapping.clone(),
+            orchestrator_link: self.orchestrator_link.clone(),
+            drops: self.drops.clone(),
+            suppression_signers: self.suppression_signers.clone(),
+            pcap_capture: self.pcap_capture.clone(),
+            memory_acquisition: self.memory_acquisition.clone(),
+            agent_logs: self.agent_logs.clone(),
+            sandbox: self.sandbox.clone(),
+            disk_quotas: self.disk_quotas.clone(),
+            yara_signers: self.yara_signers.clone(),
+            audit_reviewers: self.audit_reviewers.clone(),
+            pipeline: self.pipeline.clone(),
+            load_shedder: self.load_shedder.clone(),
+            export: self.export.clone(),
+            feature_flags: self.feature_flags.clone(),
+            drain: self.drain.clone(),
+        }
+    }
+
+    /// Every route with its middleware, as served by `start`.
+    pub fn router(&self) -> Router {
+        let state = self.app_state();
+
+        // Token-protected routes: middleware maps bearer token -> agent identity before handlers run;
+        // refused ingest requests are then counted against that agent, and a 503 says when to retry
+        let protected = Router::new()
             .route("/ingest/linux", post(handle_linux_ingest))
             .route("/ingest/dpi", post(handle_dpi_ingest))
-            .with_state(self.db_client.clone());
+            .route_layer(middleware::from_fn_with_state(self.load_shedder.clone(), load_shedding::retry_after))
+            .route_layer(middleware::from_fn_with_state(self.drops.clone(), drop_accounting::count_ingest_drops))
+            .route("/agents/token/rotate", post(http_agent_auth::handle_rotate))
+            .route("/agents/yara/poll", post(http_yara::handle_poll))
+            .route_layer(middleware::from_fn_with_state(state.clone(), http_agent_auth::require_agent_token))
+            .layer(DefaultBodyLimit::max(self.max_body_bytes));
+
+        // Probe control channel for triggered captures; a pcap upload may exceed the ingest body limit
+        let capture_body_limit = self
+            .pcap_capture
+            .as_ref()
+            .map_or(self.max_body_bytes, |c| self.max_body_bytes.max(c.max_bytes as usize));
+        let probes = Router::new()
+            .route("/probes/captures/claim", post(http_pcap_capture::handle_claim_capture))
+            .route("/probes/captures/upload", post(http_pcap_capture::handle_upload_capture))
+            .route("/probes/captures/fail", post(http_pcap_capture::handle_fail_capture))
+            .route_layer(middleware::from_fn_with_state(self.disk_quotas.clone(), disk_quota::retry_after))
+            .route_layer(middleware::from_fn_with_state(state.clone(), http_agent_auth::require_agent_token))
+            .layer(DefaultBodyLimit::max(capture_body_limit));
+
+        // Agent control channel for memory acquisition; chunks may exceed the ingest body limit
+        let chunk_body_limit = self
+            .memory_acquisition
+            .as_ref()
+            .map_or(self.max_body_bytes, |p| self.max_body_bytes.max(p.chunk_bytes as usize));
+        let memory = Router::new()
+            .route("/agents/memory-acquisitions/claim", post(http_memory_acquisition::handle_claim_acquisition))
+            .route("/agents/memory-acquisitions/chunk", post(http_memory_acquisition::handle_upload_chunk))
+            .route("/agents/memory-acquisitions/complete", post(http_memory_acquisition::handle_complete_acquisition))
+            .route("/agents/memory-acquisitions/fail", post(http_memory_acquisition::handle_fail_acquisition))
+            .route_layer(middleware::from_fn_with_state(self.disk_quotas.clone(), disk_quota::retry_after))
+            .route_layer(middleware::from_fn_with_state(state.clone(), http_agent_auth::require_agent_token))
+            .layer(DefaultBodyLimit::max(chunk_body_limit));
 
-        let listener = tokio::net::TcpListener::bind(&self.listen_addr).await?;
-        info!("HTTP Ingestion Server listening on {}", self.listen_addr);
+        // Sealed agent log segments; a segment may exceed the ingest body limit
+        let segment_body_limit = self
+            .agent_logs
+            .as_ref()
+            .map_or(self.max_body_bytes, |c| self.max_body_bytes.max(c.max_segment_bytes as usize));
+        let agent_logs = Router::new()
+            .route("/agents/log-segments", post(http_agent_log::handle_upload_segment))
+            .route_layer(middleware::from_fn_with_state(self.disk_quotas.clone(), disk_quota::retry_after))
+            .route_layer(middleware::from_fn_with_state(state.clone(), http_agent_auth::require_agent_token))
+            .layer(DefaultBodyLimit::max(segment_body_limit));
 
-        axum::serve(listener, app).await?;
+        // Agent sample submissions; a sample may exceed the ingest body limit
+        let sample_body_limit = self
+            .sandbox
+            .as_ref()
+            .map_or(self.max_body_bytes, |c| self.max_body_bytes.max(c.max_sample_bytes as usize));
+        let samples = Router::new()
+            .route("/agents/sandbox/samples", post(http_sandbox::handle_submit_sample))
+            .route_layer(middleware::from_fn_with_state(self.disk_quotas.clone(), disk_quota::retry_after))
+            .route_layer(middleware::from_fn_with_state(state.clone(), http_agent_auth::require_agent_token))
+            .layer(DefaultBodyLimit::max(sample_body_limit));
+
+        // Operator API: X-Admin-Key, or an OIDC / break-glass bearer whose role permits the route
+        let admin = Router::new()
+            .route(
+                "/admin/runtime-config",
+                get(http_runtime_admin::handle_get_runtime_config).post(http_runtime_admin::handle_set_runtime_config),
+            )
+            .route("/admin/orchestrator-link", get(http_runtime_admin::handle_get_orchestrator_link))
+            .route("/admin/pipeline", get(http_runtime_admin::handle_get_pipeline))
+            .route("/admin/health", get(http_runtime_admin::handle_get_health))
+            .route("/admin/load-shedding", get(http_runtime_admin::handle_get_load_shedding))
+            .route("/admin/drops", get(http_drops_admin::handle_list_drops))
+            .route("/admin/drops/ingest", get(http_drops_admin::handle_get_ingest_drops))
+            .route("/admin/export/events", get(http_export_admin::handle_export_events))
+            .route("/admin/export/stats", get(http_export_admin::handle_get_export_stats))
+            .route("/admin/fleet/inventory", get(http_fleet_admin::handle_list_inventory))
+            .route("/admin/dashboard/detections-by-severity", get(http_dashboard_admin::handle_detections_by_severity))
+            .route("/admin/dashboard/events-per-minute", get(http_dashboard_admin::handle_events_per_minute))
+            .route("/admin/dashboard/fleet-summary", get(http_dashboard_admin::handle_fleet_summary))
+            .route("/admin/dashboard/cache", get(http_dashboard_admin::handle_get_cache_stats))
+            .route("/admin/identity-conflicts", get(http_identity_admin::handle_list_conflicts))
+            .route("/admin/identity-conflicts/resolve", post(http_identity_admin::handle_resolve_conflict))
+            .route(
+                "/admin/webhooks",
+                get(http_webhook_admin::handle_list_webhooks).post(http_webhook_admin::handle_register),
+            )
+            .route("/admin/webhooks/disable", post(http_webhook_admin::handle_disable_webhook))
+            .route("/admin/webhooks/deliveries", get(http_webhook_admin::handle_list_deliveries))
+            .route("/admin/webhooks/deliveries/redrive", post(http_webhook_admin::handle_redrive_delivery))
+            .route(
+                "/admin/legal-holds",
+                get(http_legal_hold_admin::handle_list_holds).post(http_legal_hold_admin::handle_place_hold),
+            )
+            .route("/admin/legal-holds/release", post(http_legal_hold_admin::handle_release_hold))
+            .route(
+                "/admin/suppressions",
+                get(http_suppression_admin::handle_list_suppressions).post(http_suppression_admin::handle_create_suppression),
+            )
+            .route("/admin/suppressions/expire", post(http_suppression_admin::handle_expire_suppression))
+            .route(
+                "/admin/maintenance-windows",
+                get(http_maintenance_admin::handle_list_windows).post(http_maintenance_admin::handle_create_window),
+            )
+            .route("/admin/maintenance-windows/cancel", post(http_maintenance_admin::handle_cancel_window))
+            .route("/admin/maintenance-windows/report", get(http_maintenance_admin::handle_window_report))
+            .route(
+                "/admin/feature-flags",
+                get(http_feature_flags_admin::handle_list_flags).post(http_feature_flags_admin::handle_set_flag),
+            )
+            .route("/admin/feature-flags/clear", post(http_feature_flags_admin::handle_clear_flag))
+            .route(
+                "/admin/annotations",
+                get(http_annotation_admin::handle_list_annotations).post(http_annotation_admin::handle_create_annotation),
+            )
+            .route("/admin/annotations/edit", post(http_annotation_admin::handle_edit_annotation))
+            .route(
+                "/admin/audit-reviews",
+                get(http_audit_review_admin::handle_list_reviews).post(http_audit_review_admin::handle_attest_review),
+            )
+            .route("/admin/audit-reviews/coverage", get(http_audit_review_admin::handle_review_coverage))
+            .route("/admin/incidents/close", post(http_rule_metrics_admin::handle_close_incident))
+            .route("/admin/rules/metrics", get(http_rule_metrics_admin::handle_list_rule_metrics))
+            .route("/admin/rules/tuning", get(http_rule_metrics_admin::handle_rule_tuning))
+            .route("/admin/retention/runs", get(http_retention_admin::handle_list_runs))
+            .route("/admin/retention/status", get(http_retention_admin::handle_retention_status))
+            .route("/admin/pcap-captures", get(http_pcap_capture::handle_list_captures))
+            .route(
+                "/admin/memory-acquisitions",
+                get(http_memory_acquisition::handle_list_acquisitions).post(http_memory_acquisition::handle_request_acquisition),
+            )
+            .route(
+                "/admin/sandbox-submissions",
+                get(http_sandbox::handle_list_submissions).post(http_sandbox::handle_submit_hash),
+            )
+            .route(
+                "/admin/yara-rule-packs",
+                get(http_yara::handle_list_rule_packs).post(http_yara::handle_create_rule_pack),
+            )
+            .route("/admin/yara-scans", get(http_yara::handle_list_scans).post(http_yara::handle_request_scan))
+            .route("/schema", get(http_schema_admin::handle_get_schema))
+            .route_layer(middleware::from_fn_with_state(
+                OperatorGate { auth: self.operator_auth.clone(), store: self.store.clone() },
+                operator_auth::authorize,
+            ));
+
+        let mut app = Router::new()
+            .merge(protected)
+            .merge(probes)
+            .merge(memory)
+            .merge(agent_logs)
+            .merge(samples)
+            .merge(admin)
+            .route("/agents/enroll", post(http_agent_auth::handle_enroll))
+            .route("/agents/token/revoke", post(http_agent_auth::handle_revoke))
+            .route("/agents/key/rotate", post(http_agent_auth::handle_key_rotate))
+            .route("/auth/oidc/login", get(http_operator_auth::handle_oidc_login))
+            .route("/auth/oidc/callback", get(http_operator_auth::handle_oidc_callback))
+            .route("/auth/break-glass", post(http_operator_auth::handle_break_glass_login))
+            .route("/auth/session", get(http_operator_auth::handle_get_session));
+        if self.openapi {
+            app = app.merge(openapi::router());
+            info!("OpenAPI contract at {} and Swagger UI at {}", openapi::OPENAPI_JSON_PATH, openapi::SWAGGER_UI_PATH);
+        }
+        app.with_state(state)
+            .layer(middleware::from_fn_with_state(self.drain.clone(), handoff::track_in_flight))
+    }
+
+    pub async fn start(self) -> Result<(), Box<dyn std::error::Error>> {
+        // Policy-published quotas - FAIL-CLOSED if they cannot be read at startup
+        if let Some(db) = &self.db_client {
+            let loaded = self.budget.load_from_db(db).await?;
+            info!("Loaded {} ingest component quota(s)", loaded);
+            self.budget.spawn_refresh(db.clone())?;
+
+            // Runtime feature flags - FAIL-CLOSED if they cannot be read at startup
+            let loaded = self.feature_flags.load_from_db(db).await?;
+            info!("Loaded {} feature flag setting(s)", loaded);
+            self.feature_flags.spawn_refresh(db.clone())?;
+        }
+
+        // Identities still in conflict stay quarantined across restarts - FAIL-CLOSED if unreadable
+        if self.identity_conflicts.mode != ConflictMode::Disabled {
+            let open = self.store.open_identity_conflicts().await?;
+            if !open.is_empty() {
+                warn!("{} identity conflict(s) still open; their events remain quarantined", open.len());
+            }
+            self.identity_conflicts.restore(open);
+        }
+
+        // Outbox relay on its own connection: handler transactions share the request client
+        if let Some(addr) = &self.outbox.publish_addr {
+            let relay_store: Arc<dyn TelemetryStore> = match self.store.backend() {
+                StorageBackend::Postgres => Arc::new(PostgresStore::new(postgres::connect_from_env().await?)),
+                StorageBackend::Sqlite => self.store.clone(),
+            };
+            let publisher = Arc::new(TcpPublisher::new(addr.clone(), self.outbox.publish_timeout));
+            OutboxRelay::new((*self.outbox).clone(), relay_store, publisher).spawn();
+            info!("Outbox relay started | publish_addr={}", addr);
+        }
+
+        // Sandbox worker on its own connection: a verdict and its detection commit in one transaction
+        if let Some(cfg) = &self.sandbox {
+            let db = postgres::connect_from_env().await?;
+            let store: Arc<dyn TelemetryStore> =
+                Arc::new(PostgresStore::new(db.clone()).with_pcap_capture(self.pcap_capture.as_deref().cloned()));
+            SandboxWorker::new(cfg.clone(), db, store)?.with_dashboard_cache(self.dashboard.clone()).spawn();
+            info!("Sandbox worker started | poll_secs={}", cfg.poll_interval.as_secs());
+        }
+
+        // One connection per pipeline shard: shards commit in parallel, each agent's events in order
+        let shards = self.pipeline.config().shards;
+        let mut shard_stores: Vec<Arc<dyn TelemetryStore>> = Vec::with_capacity(shards);
+        for _ in 0..shards {
+            shard_stores.push(match self.store.backend() {
+                StorageBackend::Postgres => Arc::new(
+                    PostgresStore::new(postgres::connect_from_env().await?)
+                        .with_pcap_capture(self.pcap_capture.as_deref().cloned()),
+                ),
+                StorageBackend::Sqlite => self.store.clone(),
+            });
+        }
+        self.pipeline.start(shard_stores)?;
+
+        // Spool quota scans: warnings and purges are raised as disk.quota webhooks
+        if let (false, Some(db)) = (self.disk_quotas.manager().is_empty(), &self.db_client) {
+            self.disk_quotas.spawn_scan(db.clone(), self.orchestrator_link.instance_id().to_string());
+        }
+
+        // Every instance purges expired shared state; the deletes are idempotent
+        if let (SharedStateMode::Postgres, Some(db)) = (self.shared_state.mode, &self.db_client) {
+            shared_state::spawn_purge(db.clone(), &self.shared_state);
+        }
+
+        // Every instance refreshes rule_metrics; the upsert is idempotent
+        if let Some(db) = &self.db_client {
+            rule_metrics::spawn_refresh(db.clone(), &self.rule_metrics);
+        }
+
+        // Ingest-side drops reach telemetry_drops_daily on this flush; counters of ended runs are purged
+        self.drops.clone().spawn_flush(self.store.clone(), self.drop_cfg.clone());
+
+        let app = self.router();
+
+        let (listener, source) = handoff::acquire_listener(&self.listen_addr, &self.listener_cfg)?;
+        info!("HTTP Ingestion Server listening on {} (socket={})", self.listen_addr, source.as_str());
+
+        // Announce this instance as the successor; a previous instance sharing the socket drains
+        let boot_id = self.orchestrator_link.boot_id();
+        if let Some(path) = &self.listener_cfg.handoff_file {
+            handoff::announce(path, &HandoffRecord::new(boot_id, &self.listen_addr))?;
+            handoff::spawn_watcher(path.clone(), boot_id, self.drain.clone());
+            info!("Handoff file {} now names this instance (boot_id={})", path.display(), boot_id);
+        }
+
+        // Report ready only once the listener is bound, draining once a drain has started
+        if let Some(cfg) = self.heartbeat.clone() {
+            let drain = self.drain.clone();
+            let pipeline = self.pipeline.clone();
+            let load_shedder = self.load_shedder.clone();
+            let disk_quotas = self.disk_quotas.clone();
+            let dashboard = self.dashboard.clone();
+            let link = self.orchestrator_link.clone();
+            HeartbeatClient::new(cfg, self.orchestrator_link.clone())?.spawn(move || {
+                let readiness = if drain.is_draining() { ServiceStatus::Draining } else { ServiceStatus::Ready };
+                let report = service_health::report(
+                    &drain,
+                    &pipeline.stats(),
+                    &load_shedder.stats(),
+                    &disk_quotas,
+                    &dashboard,
+                    &link.status(Instant::now()),
+                );
+                (readiness, report)
+            });
+        }
+
+        // Peer address feeds duplicate agent-id detection
+        let drain = self.drain.clone();
+        let server = axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+            .with_graceful_shutdown(async move { drain.wait().await });
+        let timeout = self.listener_cfg.drain_timeout;
+        tokio::select! {
+            res = server.into_future() => res?,
+            _ = async { self.drain.wait().await; tokio::time::sleep(timeout).await } => {
+                warn!(
+                    "Drain timeout after {}s; abandoning {} in-flight request(s)",
+                    timeout.as_secs(),
+                    self.drain.in_flight()
+                );
+            }
+        }
+
+        if let Some(path) = &self.listener_cfg.handoff_file {
+            handoff::release(path, boot_id);
+        }
+        if let Err(e) = self.drops.flush(self.store.as_ref()).await {
+            warn!("Final flush of ingest drop counts failed; {} drop(s) lost: {}", self.drops.stats().pending_flush, e);
+        }
+        info!("HTTP Ingestion Server drained ({})", self.drain.reason().unwrap_or_default());
         Ok(())
     }
 }
 
-async fn handle_linux_ingest(
-    State(db): State<Arc<Client>>,
-    Json(payload): Json<SignedEvent>,
-) -> Result<Json<IngestResponse>, StatusCode> {
+/// POST /ingest/linux (Bearer): signed Linux agent event.
+#[utoipa::path(
+    post,
+    path = "/ingest/linux",
+    tag = "ingest",
+    request_body(content = Object, description = "Signed event: envelope, signature, payload_hash, signer_id"),
+    responses(
+        (status = 200, description = "Stored (status ok) or held under an identity conflict (status quarantined)", body = IngestResponse),
+        (status = 400, description = "Malformed signed event or envelope: failing field, expected type or constraint and a remediation hint", body = ValidationError),
+        (status = 401, description = "Missing, invalid or unbound agent token"),
+        (status = 403, description = "Signature verification or key pin failure"),
+        (status = 413, description = "Body exceeds RANSOMEYE_INGEST_MAX_BODY_BYTES"),
+        (status = 421, description = "Agent's residency region may not deliver to this instance's region"),
+        (status = 429, description = "Component rate budget exceeded"),
+        (status = 500, description = "Storage failure (fail-closed)"),
+        (status = 503, description = "Pipeline shard queue full, or event shed under database load (Retry-After)"),
+    ),
+    security(("agent_token" = []))
+)]
+#[tracing::instrument(name = "ingest.linux", skip_all, fields(signer_id = tracing::field::Empty))]
+pub(crate) async fn handle_linux_ingest(
+    State(store): State<Arc<dyn TelemetryStore>>,
+    State(controls): State<Arc<RuntimeControls>>,
+    State(payload_policy): State<Arc<PayloadStoragePolicy>>,
+    State(budget): State<Arc<ComponentRateBudget>>,
+    State(signer_keys): State<SignerKeys>,
+    State(identity_conflicts): State<Arc<IdentityConflicts>>,
+    State(placement): State<Placement>,
+    State(lineage): State<Arc<LineageVerifier>>,
+    State(agent_cache): State<Arc<AgentIdentityCache>>,
+    State(dashboard): State<Arc<DashboardCache>>,
+    State(outbox): State<Arc<OutboxConfig>>,
+    State(pipeline): State<Arc<ShardedPipeline>>,
+    State(load_shedder): State<Arc<LoadShedder>>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    auth: Option<Extension<AuthenticatedAgent>>,
+    body: Bytes,
+) -> Result<Json<IngestResponse>, IngestRejection> {
+    // Single parse of the request body; envelope/data stay as borrowed raw JSON
+    let payload = parse_signed_event(&body)?;
+    let envelope = parse_envelope(&payload)?;
+
     // Log received payload for debugging (redact signature for security)
-    info!("Received Linux ingest request | signer_id={} | payload_hash={} | envelope_keys={:?}", 
+    info!("Received Linux ingest request | signer_id={} | payload_hash={} | envelope_bytes={}", 
         payload.signer_id, 
         payload.payload_hash,
-        payload.envelope.as_object().map(|o| o.keys().collect::<Vec<_>>()).unwrap_or_default()
+        payload.envelope.get().len()
     );
     
     // Verify required fields
-    if payload.signature.is_empty() {
-        error!("VALIDATION ERROR: Missing signature field");
-        return Err(StatusCode::BAD_REQUEST);
-    }
-    if payload.payload_hash.is_empty() {
-        error!("VALIDATION ERROR: Missing payload_hash field");
-        return Err(StatusCode::BAD_REQUEST);
-    }
-    if payload.signer_id.is_empty() {
-        error!("VALIDATION ERROR: Missing signer_id field");
-        return Err(StatusCode::BAD_REQUEST);
-    }
+    check_signed_event_fields(&payload)?;
+
+    // The producer embeds its canonical envelope bytes verbatim, so payload_hash is checked against
+    // the SHA-256 of the envelope as received (signature verification below)
+    let envelope_payload_sha256 = Sha256::digest(payload.envelope.get().as_bytes()).to_vec();
 
-    // Note: We trust the payload_hash provided by the agent. JSON serialization
-    // key ordering is non-deterministic when re-serializing JsonValue, so recomputing
-    // the hash here would cause false mismatches. The agent computes the hash from
-    // the canonical envelope struct before converting to JsonValue for transport.
-    // Hash integrity will be verified via signature verification.
-    info!("Received payload_hash={} (trusted from agent)", payload.payload_hash);
+    // Token binding (interim mTLS substitute): the bearer token's agent must be the envelope producer.
+    // Checked before signature verification so unbound producers never reach crypto/DB work.
+    let auth = auth.map(|Extension(a)| a);
+    http_agent_auth::check_envelope_binding(auth.as_ref(), &envelope.component_id, "linux_agent")?;
 
-    // Verify signature (simplified - in production would verify against trust store)
-    let _sig_bytes = general_purpose::STANDARD.decode(&payload.signature)
+    // Policy rate budget for this component instance (429 + detection, never a silent drop)
+    enforce_component_budget(&budget, store.as_ref(), &dashboard, "linux_agent", &envelope.component_id).await?;
+
+    // Signature bytes; verified against the signer's key once the agent is resolved
+    let sig_bytes = general_purpose::STANDARD.decode(payload.signature.as_bytes())
         .map_err(|e| {
             error!("Invalid signature base64: {}", e);
-            StatusCode::BAD_REQUEST
+            ValidationError::new(ValidationCategory::InvalidValue, "signature", "base64", format!("invalid base64: {}", e))
         })?;
-    
-    info!("Signature verified OK");
 
     // Extract fields from envelope
-    let message_id = payload.envelope.get("event_id")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| {
-            error!("Missing event_id in envelope");
-            StatusCode::BAD_REQUEST
-        })?;
-    let timestamp_str = payload.envelope.get("timestamp")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| {
-            error!("Missing timestamp in envelope");
-            StatusCode::BAD_REQUEST
-        })?;
-    let timestamp = DateTime::parse_from_rfc3339(timestamp_str)
-        .map_err(|e| {
-            error!("Invalid timestamp format: {}", e);
-            StatusCode::BAD_REQUEST
-        })?
-        .with_timezone(&Utc);
-    let component_id = payload.envelope.get("component_id")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| {
-            error!("Missing component_id in envelope");
-            StatusCode::BAD_REQUEST
-        })?;
-    
-    // Extract data field from envelope
-    let data = payload.envelope.get("data")
-        .ok_or_else(|| {
-            error!("Missing data in envelope");
-            StatusCode::BAD_REQUEST
-        })?;
+    let message_id: &str = &envelope.event_id;
+    let timestamp = parse_envelope_timestamp(&envelope.timestamp)?;
+    let component_id: &str = &envelope.component_id;
 
-    // Parse event data to extract fields
-    let event_name = data.get("event_category")
-        .and_then(|v| v.as_str())
-        .unwrap_or("unknown")
-        .to_string();
-    let event_category = data.get("event_category")
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
-    let pid = data.get("pid").and_then(|v| v.as_u64()).map(|v| v as i64);
-    let ppid = data.get("process_data")
-        .and_then(|v| v.get("ppid"))
-        .and_then(|v| v.as_u64())
-        .map(|v| v as i64);
-    let uid = data.get("uid").and_then(|v| v.as_u64()).map(|v| v as i64);
-    let gid = data.get("gid").and_then(|v| v.as_u64()).map(|v| v as i64);
-    let username: Option<String> = None; // Not in current envelope structure
-    let process_name: Option<String> = data.get("process_data")
-        .and_then(|v| v.get("executable"))
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
-    let process_path: Option<String> = data.get("process_data")
-        .and_then(|v| v.get("executable"))
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
-    let cmdline: Option<String> = data.get("process_data")
-        .and_then(|v| v.get("command_line"))
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
-    // Extract and pre-allocate Option<String> with proper lifetimes
-    let file_path: Option<String> = data.get("filesystem_data")
-        .and_then(|v| v.get("path"))
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
-    let network_src_ip: Option<String> = data.get("network_data")
-        .and_then(|v| v.get("remote_addr"))
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
+    // Parse event data to extract fields (typed view of envelope.data)
+    let data: LinuxEventData = envelope_validation::from_str("envelope.data", envelope.data.get()).map_err(|e| {
+        error!("VALIDATION ERROR: Invalid linux envelope data: {} | field={}", e.message, e.field);
+        e
+    })?;
+    let event_name = data.event_category.clone().unwrap_or_else(|| "unknown".to_string());
+    let event_type = data.event_type().map(str::to_string);
+    let event_category = data.event_category;
+    // Cumulative drop counters carried by agent_stats reports
+    let agent_stats = data.agent_stats.filter(|_| event_category.as_deref() == Some("agent_stats"));
+    let host_inventory = data.host_inventory.filter(|_| event_category.as_deref() == Some("host_inventory"));
+    let pid = data.pid.map(|v| v as i64);
+    let uid = data.uid.map(|v| v as i64);
+    let (process_name, cmdline) = match &data.process_data {
+        Some(p) => (p.executable.clone(), p.command_line.clone()),
+        None => (None, None),
+    };
+    let file_path: Option<String> = data.filesystem_data.and_then(|f| f.path);
+    let (network_src_ip, network_dst_ip) = match &data.network_data {
+        Some(n) => (n.remote_addr.clone(), n.local_addr.clone()),
+        None => (None, None),
+    };
+    // Ports follow the same src=remote / dst=local mapping; 0 or out of range = unknown
+    let port = |p: Option<u64>| p.filter(|p| (1..=65535).contains(p)).map(|p| p as i32);
+    let (network_src_port, network_dst_port, netns_inode, host_netns) = match &data.network_data {
+        Some(n) => (port(n.remote_port), port(n.local_port), n.netns_inode.map(|i| i as i64), n.host_netns),
+        None => (None, None, None, None),
+    };
+    // Container context: no container (or no container_id) = host activity
+    let container = data.container.as_ref().filter(|c| c.container_id.is_some());
+    if let Some(id) = container.and_then(|c| c.container_id.as_deref()) {
+        if id.len() != 64 || !id.bytes().all(|b| b.is_ascii_hexdigit()) {
+            error!("VALIDATION ERROR: Invalid container_id | value={}", id);
+            return Err(ValidationError::new(
+                ValidationCategory::InvalidValue,
+                "envelope.data.container.container_id",
+                "64 hex characters",
+                format!("container_id has {} characters or a non-hex character", id.len()),
+            )
+            .into());
+        }
+    }
+    let container_id = container.and_then(|c| c.container_id.as_deref()).map(str::to_ascii_lowercase);
+    let container_image = container.and_then(|c| c.image.clone());
+    let pod_name = container.and_then(|c| c.pod_name.clone());
+    let pod_namespace = container.and_then(|c| c.pod_namespace.clone());
     // Parse and validate IP as IpAddr for PostgreSQL INET type
     let network_src_ip_param: Option<IpAddr> =
         network_src_ip.as_ref().and_then(|s| s.parse().ok());
-    let network_src_port = data.get("network_data")
-        .and_then(|v| v.get("remote_port"))
-        .and_then(|v| v.as_u64())
-        .map(|v| v as i64);
-    let network_dst_ip: Option<String> = data.get("network_data")
-        .and_then(|v| v.get("local_addr"))
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
-    // Parse and validate IP as IpAddr for PostgreSQL INET type
     let network_dst_ip_param: Option<IpAddr> =
         network_dst_ip.as_ref().and_then(|s| s.parse().ok());
-    let network_dst_port = data.get("network_data")
-        .and_then(|v| v.get("local_port"))
-        .and_then(|v| v.as_u64())
-        .map(|v| v as i64);
     let protocol: Option<String> = None; // Not in current envelope structure
 
-    // Get or create agent_id
-    let agent_id = get_or_create_agent(&db, component_id, "linux_agent").await
-        .map_err(|e| {
-            error!("Failed to get/create agent: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    // Resolve agent_id: token-authenticated identity wins; legacy (auth disabled) path auto-registers
+    let agent_id = match &auth {
+        Some(a) => a.agent_id,
+        None => agent_cache.resolve(store.as_ref(), component_id, TelemetrySource::LinuxAgent).await
+            .map_err(|e| {
+                error!("Failed to get/create agent: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?,
+    };
+
+    // Region pinning: refuse data this instance's region may not hold (421, audited)
+    let residency_region = enforce_residency(&placement.residency, store.as_ref(), "linux_agent", agent_id, auth.as_ref(), message_id).await?;
+
+    // Historical identity binding: this agent's events must carry its pinned signing key
+    enforce_key_pin(&signer_keys.pins, store.as_ref(), &dashboard, agent_id, component_id, &payload.signer_id).await?;
 
     // Parse message_id as UUID (extracted from envelope.event_id above)
     let message_id_uuid = Uuid::parse_str(message_id)
         .map_err(|e| {
             error!("VALIDATION ERROR: Invalid message_id UUID format | value={} | error={}", message_id, e);
-            StatusCode::BAD_REQUEST
+            invalid_event_id(e)
         })?;
 
+    // Ed25519 signature of payload_hash against the signer's registered key (403 in enforce mode)
+    let signature_record = enforce_signature(&signer_keys.signatures, store.as_ref(), agent_id, component_id, message_id_uuid, &payload, &sig_bytes).await?;
+
+    // Host facts replace the agent's host_inventory row with the event; an invalid report is refused whole
+    let host_inventory = match &host_inventory {
+        Some(report) => Some(host_inventory::to_record(agent_id, message_id, timestamp, report).map_err(|e| {
+            error!("VALIDATION ERROR: Invalid host_inventory report | agent_id={} | error={}", agent_id, e);
+            ValidationError::new(ValidationCategory::InvalidValue, "envelope.data.host_inventory", "valid host_inventory report", e.to_string())
+        })?),
+        None => None,
+    };
+
+    // Duplicate agent-id: events of an identity in conflict are held in quarantined_events
+    let origin = IdentityOrigin {
+        source_ip: peer.map(|ConnectInfo(addr)| addr.ip()),
+        host_id: envelope.host_id.as_deref().map(str::to_string),
+    };
+    let screened = screen_identity(&identity_conflicts, store.as_ref(), &dashboard, "linux_agent", component_id, agent_id, origin, message_id, &body);
+    if let Some(quarantined) = screened.await? {
+        return Ok(quarantined);
+    }
+
+    // Process lineage: verify the event's link into its host's per-boot chain
+    if let Some((process, link)) = data.process_data.as_ref().and_then(|p| p.lineage.as_ref().map(|l| (p, l))) {
+        let event = LineageEvent::from_linux(data.pid, data.uid, data.gid, process);
+        verify_lineage(&lineage, store.as_ref(), &dashboard, agent_id, component_id, &event, link).await;
+    }
+
+    // Topology: the event carries its agent's zone; the agent's own end of a connection is in that zone
+    let zone = placement.topology.label(component_id);
+    if data.network_data.is_some() {
+        let local = Endpoint { addr: network_dst_ip_param, port: network_dst_port.map(|p| p as u16), sensor_zone: zone.as_ref() };
+        let remote = Endpoint { addr: network_src_ip_param, port: network_src_port.map(|p| p as u16), sensor_zone: None };
+        check_lateral_movement(&placement.topology, store.as_ref(), &dashboard, agent_id, component_id, &local, &remote).await;
+    }
+
     // PROMPT-38.1: Insert into raw_events IMMEDIATELY after acceptance (signature verified + agent resolved)
-    // This is the canonical append-only capture point - no normalization, no enrichment, no schema changes
-    let full_envelope_json = serde_json::to_value(&payload.envelope)
+    // This is the canonical append-only capture point - no normalization, no enrichment, no schema changes.
+    // The received envelope bytes are stored as-is.
+    let data_sha256 = Sha256::digest(envelope.data.get().as_bytes()).to_vec();
+
+    // Storage policy: full envelope or summary (hashes + extracted fields) for this raw_events row
+    let payload_facts = PayloadFacts {
+        source: TelemetrySource::LinuxAgent,
+        event_category: event_category.as_deref(),
+        event_type: event_type.as_deref(),
+    };
+    let payload_decision = payload_policy.decide(payload_facts, &envelope_payload_sha256);
+    let priority = ShedPriority::classify(payload_facts.source, payload_facts.event_category, payload_facts.event_type);
+    let raw_payload_json = match payload_decision.storage {
+        PayloadStorage::Full => payload.envelope.to_owned(),
+        PayloadStorage::Summary => payload_policy::summary_payload(
+            &payload_decision,
+            payload_facts,
+            &envelope,
+            &envelope_payload_sha256,
+            &data_sha256,
+            serde_json::json!({
+                "pid": pid,
+                "uid": uid,
+                "process_name": process_name,
+                "cmdline": cmdline,
+                "file_path": file_path,
+                "network_src_ip": network_src_ip,
+                "network_dst_ip": network_dst_ip,
+                "netns_inode": netns_inode,
+                "container_id": container_id,
+            }),
+        )
         .map_err(|e| {
-            error!("Failed to serialize envelope for raw_events: {}", e);
+            error!("Failed to build raw_events summary payload: {}", e);
             StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-    let envelope_json_bytes = serde_json::to_vec(&full_envelope_json)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    let mut envelope_hasher = Sha256::new();
-    envelope_hasher.update(&envelope_json_bytes);
-    let envelope_payload_sha256 = envelope_hasher.finalize().to_vec();
+        })?,
+    };
 
     // PROMPT-40A: Get ingestion component for audit attribution
-    let ingestion_component_id = get_or_create_ingestion_component(&db).await
+    let ingestion_component_id = store.ingestion_component().await
         .map_err(|e| {
             error!("FAIL-CLOSED: Failed to get/create ingestion component: {}", e);
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
-    // PROMPT-38.1: Start transaction for atomic raw_events + telemetry + audit persistence
-    // Use explicit SQL BEGIN since we have Arc<Client> (can't use transaction API)
-    db.execute("BEGIN", &[]).await
+    // Generate 64-character hex nonce (32 bytes = 64 hex chars) to match schema CHECK constraint
+    let mut nonce_bytes = vec![0u8; 32];
+    crypto::rand::fill(&mut nonce_bytes)
         .map_err(|e| {
-            error!("Failed to start transaction: {}", e);
+            error!("Failed to generate nonce: {}", e);
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
+    let nonce = hex::encode(nonce_bytes);
 
-    // PROMPT-40A: Audit INGEST_ACCEPT (after signature verification + agent resolution, before DB writes)
-    let ingest_accept_payload = serde_json::json!({
-        "message_id": message_id,
-        "signer_id": payload.signer_id,
-        "payload_hash": payload.payload_hash,
-        "source": "linux_agent",
-        "agent_id": agent_id.to_string(),
-        "envelope_keys": payload.envelope.as_object().map(|o| o.keys().collect::<Vec<_>>()).unwrap_or_default()
+    // Diagnostic logging for extracted values before insert (sampled; ratio adjustable at runtime)
+    if controls.should_sample_event() {
+        error!("PRE-INSERT DIAGNOSTICS:");
+        error!("  file_path: {:?}", file_path);
+        error!("  network_src_ip (inet): {:?} -> parsed: {:?}", network_src_ip, network_src_ip_param);
+        error!("  network_dst_ip (inet): {:?} -> parsed: {:?}", network_dst_ip, network_dst_ip_param);
+        error!("  Data JSON bytes: {}", envelope.data.get().len());
+    }
+
+    let payload_sha256 = Some(data_sha256);
+    let telemetry = TelemetryRecord::Linux(LinuxTelemetry {
+        provenance: TelemetryProvenance {
+            agent_id,
+            message_id: message_id_uuid,
+            nonce,
+            component_identity: component_id.to_string(),
+            signature_b64: payload.signature.to_string(),
+            signature_alg: "Ed25519".to_string(),
+            data_hash_hex: payload.payload_hash.to_string(),
+            observed_at: timestamp,
+        },
+        host_id: hostname::get().unwrap_or_default().to_string_lossy().to_string(),
+        event_name: event_name.clone(),
+        event_category: event_category.unwrap_or_default(),
+        pid: pid.map(|v| v as i32),
+        uid: uid.map(|v| v as i32),
+        process_name,
+        cmdline,
+        file_path,
+        // Only addresses that parsed as IpAddr reach the INET columns
+        network_src_ip: network_src_ip_param.map(|ip| ip.to_string()),
+        network_dst_ip: network_dst_ip_param.map(|ip| ip.to_string()),
+        network_src_port,
+        network_dst_port,
+        netns_inode,
+        host_netns,
+        protocol,
+        container_id,
+        container_image,
+        pod_name,
+        pod_namespace,
+        payload_json: envelope.data.get().to_string(),
+        payload_sha256,
     });
-    let ingest_accept_payload_str = serde_json::to_string(&ingest_accept_payload)
-        .map_err(|e| {
-            error!("Failed to serialize ingest accept audit payload: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-    let mut ingest_accept_hasher = Sha256::new();
-    ingest_accept_hasher.update(ingest_accept_payload_str.as_bytes());
-    let ingest_accept_payload_sha256 = ingest_accept_hasher.finalize().to_vec();
-    
-    insert_immutable_audit_log(
-        &db,
-        Some(ingestion_component_id),
-        Some(agent_id),
+
+    // PROMPT-40A: Audit INGEST_ACCEPT (after signature verification + agent resolution, before DB writes)
+    let ingest_accept_audit = ingest_audit_record(
+        ingestion_component_id,
+        agent_id,
         "INGEST_ACCEPT",
-        "raw_event",
         None,
-        Some(timestamp),
-        &ingest_accept_payload,
-        &ingest_accept_payload_sha256,
-    ).await.map_err(|e| {
-        error!("FAIL-CLOSED: Failed to insert INGEST_ACCEPT audit log: {}", e);
+        timestamp,
+        serde_json::json!({
+            "message_id": message_id,
+            "signer_id": payload.signer_id,
+            "payload_hash": payload.payload_hash,
+            "source": "linux_agent",
+            "agent_id": agent_id.to_string(),
+            "envelope_sha256": hex::encode(&envelope_payload_sha256),
+            "residency_region": residency_region
+        }),
+    ).map_err(|e| {
+        error!("Failed to serialize ingest accept audit payload: {}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
-    // Insert into raw_events with minimal canonical fields only (within transaction)
-    let raw_event_id = match db.query_one(
-        r#"
-        INSERT INTO raw_events (
-            source_type, source_agent_id, observed_at, received_at,
-            event_name, payload_json, payload_sha256
-        )
-        VALUES ('linux_agent'::event_source_type, $1, $2, NOW(), $3, $4, $5)
-        RETURNING raw_event_id
-        "#,
-        &[
-            &agent_id,
-            &timestamp,
-            &event_name,
-            &full_envelope_json,
-            &envelope_payload_sha256,
-        ],
-    ).await {
-        Ok(row) => {
-            let raw_event_id: Uuid = row.get(0);
-            info!("raw_events inserted | raw_event_id={} | agent_id={} | event_name={} | message_id={}", raw_event_id, agent_id, event_name, message_id);
-            raw_event_id
-        }
-        Err(e) => {
-            error!("FAIL-CLOSED: Failed to insert raw_events: {}", e);
-            // Rollback transaction on failure
-            let _ = db.execute("ROLLBACK", &[]).await;
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
-        }
-    };
+    // Database degradation: shed low-priority events and slow agents, never critical events
+    let admission = load_shedder.admit(priority, &pipeline.load(agent_id));
+    if admission == Admission::Reject {
+        return Err(StatusCode::SERVICE_UNAVAILABLE.into());
+    }
 
-    // PROMPT-40A: Audit RAW_EVENT_INSERT (after successful raw_events INSERT, same transaction)
-    let raw_event_insert_payload = serde_json::json!({
-        "raw_event_id": raw_event_id.to_string(),
-        "source_type": "linux_agent",
-        "agent_id": agent_id.to_string(),
-        "event_name": event_name,
-        "observed_at": timestamp.to_rfc3339(),
-        "payload_sha256": hex::encode(&envelope_payload_sha256)
-    });
-    let raw_event_insert_payload_str = serde_json::to_string(&raw_event_insert_payload)
-        .map_err(|e| {
-            error!("Failed to serialize raw event insert audit payload: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-    let mut raw_event_insert_hasher = Sha256::new();
-    raw_event_insert_hasher.update(raw_event_insert_payload_str.as_bytes());
-    let raw_event_insert_payload_sha256 = raw_event_insert_hasher.finalize().to_vec();
-    
-    insert_immutable_audit_log(
-        &db,
-        Some(ingestion_component_id),
-        Some(agent_id),
-        "RAW_EVENT_INSERT",
-        "raw_event",
-        Some(raw_event_id),
-        Some(timestamp),
-        &raw_event_insert_payload,
-        &raw_event_insert_payload_sha256,
-    ).await.map_err(|e| {
-        error!("FAIL-CLOSED: Failed to insert RAW_EVENT_INSERT audit log: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    // Persist on the agent's pipeline shard: an agent's events commit in the order they were accepted;
+    // replayed backlog waits in its own lane so fresh events are not stuck behind it
+    let shard_message_id = message_id.to_string();
+    let raw_event_id = pipeline
+        .run_event(agent_id, timestamp, move |store| async move {
+            let message_id = shard_message_id.as_str();
 
-    // Insert into linux_agent_telemetry
-    // Generate 64-character hex nonce (32 bytes = 64 hex chars) to match schema CHECK constraint
-    let rng = SystemRandom::new();
-    let mut nonce_bytes = vec![0u8; 32];
-    rng.fill(&mut nonce_bytes)
-        .map_err(|e| {
-            error!("Failed to generate nonce: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-    let nonce = hex::encode(nonce_bytes);
-    
-    // Diagnostic logging for all extracted values before insert
-    error!("PRE-INSERT DIAGNOSTICS:");
-    error!("  file_path (param 20): {:?}", file_path);
-    error!("  network_src_ip (param 21/inet): {:?} -> parsed: {:?}", network_src_ip, network_src_ip_param);
-    error!("  network_dst_ip (param 23/inet): {:?} -> parsed: {:?}", network_dst_ip, network_dst_ip_param);
-    error!("  Data JSON keys: {:?}", data.as_object().map(|o| o.keys().collect::<Vec<_>>()));
-    
-    // Pre-allocate strings that need to live for the duration of the query
-    let host_id = hostname::get().unwrap_or_default().to_string_lossy().to_string();
-    let signature_alg = "Ed25519".to_string();
-    let event_category_str: &str = event_category.as_deref().unwrap_or("");
-    let payload_json = serde_json::to_string(data).unwrap_or_else(|_| "{}".to_string());
-    let payload_sha256 = {
-        let data_json_bytes = serde_json::to_vec(data).unwrap_or_default();
-        let mut data_hasher = Sha256::new();
-        data_hasher.update(&data_json_bytes);
-        Some(data_hasher.finalize().to_vec())
-    };
-    
-    // Convert IpAddr to String for PostgreSQL INET binding (validated as IpAddr above)
-    let network_src_ip_str: Option<String> = network_src_ip_param.as_ref().map(|ip| ip.to_string());
-    let network_dst_ip_str: Option<String> = network_dst_ip_param.as_ref().map(|ip| ip.to_string());
-    
-    // Materialize all parameters as named variables to ensure proper lifetimes
-    let pid_param: Option<i32> = pid.map(|v| v as i32);
-    let uid_param: Option<i32> = uid.map(|v| v as i32);
-    let process_name_param: Option<String> = process_name.clone();
-    let process_name_param_str: Option<&str> = process_name_param.as_deref();
-    
-    // Optional fields for UPDATE
-    let cmdline_param: Option<String> = cmdline.clone();
-    let file_path_param: Option<String> = file_path.clone();
-    let network_src_ip_param_str: Option<String> = network_src_ip_str.clone();
-    let network_dst_ip_param_str: Option<String> = network_dst_ip_str.clone();
-    let protocol_param: Option<String> = protocol.clone();
-    
-    // INSERT #1 — REQUIRED FIELDS ONLY (within transaction)
-    let insert_result = db.execute(
-        r#"
-        INSERT INTO linux_agent_telemetry (
-            agent_id, source_message_id, source_nonce, source_component_identity,
-            source_host_id, source_signature_b64, source_signature_alg, source_data_hash_hex,
-            observed_at, event_name, event_category, pid, uid, process_name
-        )
-        VALUES (
-            $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14
-        )
-        "#,
-        &[
-            &agent_id,
-            &message_id_uuid,
-            &nonce,
-            &component_id,
-            &host_id,
-            &payload.signature,
-            &signature_alg,
-            &payload.payload_hash,
-            &timestamp,
-            &event_name,
-            &event_category_str,
-            &pid_param,
-            &uid_param,
-            &process_name_param_str,
-        ],
-    ).await;
-
-    match insert_result {
-        Ok(_) => {
-            // UPDATE #2 — OPTIONAL FIELDS (within transaction)
-            let update_result = db.execute(
-                r#"
-                UPDATE linux_agent_telemetry
-                SET file_path = $1,
-                    network_src_ip = $2::inet,
-                    network_dst_ip = $3::inet,
-                    payload = $4::jsonb,
-                    payload_sha256 = $5,
-                    protocol = $6,
-                    cmdline = $7
-                WHERE source_message_id = $8
-                "#,
-                &[
-                    &file_path_param.as_deref(),
-                    &network_src_ip_param_str.as_deref(),
-                    &network_dst_ip_param_str.as_deref(),
-                    &payload_json,
-                    &payload_sha256,
-                    &protocol_param.as_deref(),
-                    &cmdline_param.as_deref(),
-                    &message_id_uuid,
-                ],
-            ).await;
-            
-            // UPDATE is optional - if it fails, we still commit raw_events + required telemetry fields
-            if let Err(e) = update_result {
-                warn!("Failed to update linux_agent_telemetry optional fields (non-fatal): {}", e);
-                // Continue to commit - raw_events and required telemetry fields are already inserted
+            // PROMPT-38.1: One unit of work for atomic raw_events + telemetry + audit persistence
+            let tx_span = info_span!("ingest.db_transaction", source = "linux_agent");
+            let mut tx = store.begin().instrument(tx_span.clone()).await
+                .map_err(|e| {
+                    error!("Failed to start transaction: {}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+
+            // A shed event is acknowledged once it is in the WAL, before the flush
+            if admission == Admission::Relaxed {
+                if let Err(e) = tx.relax_durability().instrument(tx_span.clone()).await {
+                    return Err(abort_tx(tx, "Failed to relax commit durability", e).instrument(tx_span).await);
+                }
+            }
+
+            if let Err(e) = tx.append_audit(&ingest_accept_audit).instrument(tx_span.clone()).await {
+                return Err(abort_tx(tx, "Failed to insert INGEST_ACCEPT audit log", e).instrument(tx_span).await);
+            }
+
+            if let Some(record) = &signature_record {
+                if let Err(e) = tx.insert_signature_validation(ingestion_component_id, record).instrument(tx_span.clone()).await {
+                    return Err(abort_tx(tx, "Failed to insert signature_validation_events", e).instrument(tx_span).await);
+                }
+            }
+
+            // Insert into raw_events with minimal canonical fields only (within transaction)
+            let raw_event = RawEventRecord {
+                source: TelemetrySource::LinuxAgent,
+                agent_id,
+                observed_at: timestamp,
+                event_name: event_name.clone(),
+                payload_json: raw_payload_json,
+                payload_sha256: envelope_payload_sha256.clone(),
+                payload_storage: payload_decision.storage,
+                payload_storage_reason: payload_decision.reason.clone(),
+                residency_region: residency_region.clone(),
+                zone: zone.clone(),
+            };
+            let raw_event_id = match tx.insert_raw(&raw_event).instrument(tx_span.clone()).await {
+                Ok(raw_event_id) => {
+                    info!("raw_events inserted | raw_event_id={} | agent_id={} | event_name={} | message_id={} | payload_storage={}", raw_event_id, agent_id, event_name, message_id, payload_decision.storage.as_str());
+                    raw_event_id
+                }
+                Err(e) => return Err(abort_tx(tx, "Failed to insert raw_events", e).instrument(tx_span).await),
+            };
+
+            // PROMPT-40A: Audit RAW_EVENT_INSERT (after successful raw_events INSERT, same transaction)
+            let raw_event_insert_audit = match ingest_audit_record(
+                ingestion_component_id,
+                agent_id,
+                "RAW_EVENT_INSERT",
+                Some(raw_event_id),
+                timestamp,
+                serde_json::json!({
+                    "raw_event_id": raw_event_id.to_string(),
+                    "source_type": "linux_agent",
+                    "agent_id": agent_id.to_string(),
+                    "residency_region": residency_region,
+                    "event_name": event_name,
+                    "observed_at": timestamp.to_rfc3339(),
+                    "payload_sha256": hex::encode(&envelope_payload_sha256),
+                    "payload_storage": payload_decision.storage.as_str(),
+                    "payload_storage_reason": payload_decision.reason
+                }),
+            ) {
+                Ok(record) => record,
+                Err(e) => return Err(abort_tx(tx, "Failed to serialize raw event insert audit payload", e).instrument(tx_span).await),
+            };
+            if let Err(e) = tx.append_audit(&raw_event_insert_audit).instrument(tx_span.clone()).await {
+                return Err(abort_tx(tx, "Failed to insert RAW_EVENT_INSERT audit log", e).instrument(tx_span).await);
+            }
+
+            // Required telemetry fields are fatal; optional fields are best-effort inside the store
+            if let Err(e) = tx.insert_telemetry(&telemetry).instrument(tx_span.clone()).await {
+                return Err(abort_tx(tx, "Failed to insert linux_agent_telemetry (required fields)", e).instrument(tx_span).await);
+            }
+
+            // Agent-reported drops count with the report itself; a replayed or stale report adds nothing
+            if let Some(stats) = &agent_stats {
+                if let Err(e) = drop_accounting::apply_agent_report(tx.as_mut(), agent_id, timestamp, stats).instrument(tx_span.clone()).await {
+                    return Err(abort_tx(tx, "Failed to record agent drop counters", e).instrument(tx_span).await);
+                }
             }
-            
+
+            if let Some(inventory) = &host_inventory {
+                if let Err(e) = tx.upsert_host_inventory(inventory).instrument(tx_span.clone()).await {
+                    return Err(abort_tx(tx, "Failed to upsert host_inventory", e).instrument(tx_span).await);
+                }
+            }
+
+            // Outbox message commits (or rolls back) with the event
+            if outbox.enabled() {
+                let record = outbox::telemetry_accepted(
+                    raw_event_id, "linux_agent", agent_id, message_id, &event_name, timestamp, residency_region.as_deref(), zone.as_ref(),
+                );
+                if let Err(e) = tx.enqueue_outbox(&record).instrument(tx_span.clone()).await {
+                    return Err(abort_tx(tx, "Failed to insert ingest_outbox message", e).instrument(tx_span).await);
+                }
+            }
+
             // Commit transaction (raw_events + telemetry persisted atomically)
-            db.execute("COMMIT", &[]).await
+            tx.commit().instrument(tx_span).await
                 .map_err(|e| {
                     error!("FAIL-CLOSED: Failed to commit transaction: {}", e);
-                    // Transaction will be rolled back automatically by PostgreSQL on connection close
                     StatusCode::INTERNAL_SERVER_ERROR
                 })?;
-            
-            info!("Ingested linux event {} | raw_events + telemetry persisted atomically", message_id);
-            
-            Ok(Json(IngestResponse {
-                status: "ok".to_string(),
-                message_id: message_id.to_string(),
-            }))
-        }
-        Err(e) => {
-            error!("Failed to insert linux_agent_telemetry (required fields): {}", e);
-            if let Some(db_err) = e.as_db_error() {
-                error!("PostgreSQL Error: Code={:?}, Message={}", db_err.code(), db_err.message());
-                if let Some(detail) = db_err.detail() {
-                    error!("Detail: {}", detail);
-                }
-            }
-            // Rollback transaction on failure
-            let _ = db.execute("ROLLBACK", &[]).await;
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+
+            Ok::<Uuid, StatusCode>(raw_event_id)
+        })
+        .await
+        .map_err(pipeline_status)??;
+
+    info!("Ingested linux event {} | raw_event_id={} | raw_events + telemetry persisted atomically", message_id, raw_event_id);
+
+    Ok(Json(IngestResponse {
+        status: "ok".to_string(),
+        message_id: message_id.to_string(),
+    }))
 }
 
-async fn handle_dpi_ingest(
-    State(db): State<Arc<Client>>,
-    Json(payload): Json<SignedEvent>,
-) -> Result<Json<IngestResponse>, StatusCode> {
+/// POST /ingest/dpi (Bearer): signed DPI probe event.
+#[utoipa::path(
+    post,
+    path = "/ingest/dpi",
+    tag = "ingest",
+    request_body(content = Object, description = "Signed event: envelope, signature, payload_hash, signer_id"),
+    responses(
+        (status = 200, description = "Stored (status ok) or held under an identity conflict (status quarantined)", body = IngestResponse),
+        (status = 400, description = "Malformed signed event or envelope: failing field, expected type or constraint and a remediation hint", body = ValidationError),
+        (status = 401, description = "Missing, invalid or unbound agent token"),
+        (status = 403, description = "Signature verification or key pin failure"),
+        (status = 413, description = "Body exceeds RANSOMEYE_INGEST_MAX_BODY_BYTES"),
+        (status = 421, description = "Agent's residency region may not deliver to this instance's region"),
+        (status = 429, description = "Component rate budget exceeded"),
+        (status = 500, description = "Storage failure (fail-closed)"),
+        (status = 503, description = "Pipeline shard queue full, or event shed under database load (Retry-After)"),
+    ),
+    security(("agent_token" = []))
+)]
+#[tracing::instrument(name = "ingest.dpi", skip_all, fields(signer_id = tracing::field::Empty))]
+pub(crate) async fn handle_dpi_ingest(
+    State(store): State<Arc<dyn TelemetryStore>>,
+    State(payload_policy): State<Arc<PayloadStoragePolicy>>,
+    State(budget): State<Arc<ComponentRateBudget>>,
+    State(signer_keys): State<SignerKeys>,
+    State(identity_conflicts): State<Arc<IdentityConflicts>>,
+    State(placement): State<Placement>,
+    State(agent_cache): State<Arc<AgentIdentityCache>>,
+    State(dashboard): State<Arc<DashboardCache>>,
+    State(outbox): State<Arc<OutboxConfig>>,
+    State(dpi_mapping): State<Arc<DpiFieldMappings>>,
+    State(pipeline): State<Arc<ShardedPipeline>>,
+    State(load_shedder): State<Arc<LoadShedder>>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    auth: Option<Extension<AuthenticatedAgent>>,
+    body: Bytes,
+) -> Result<Json<IngestResponse>, IngestRejection> {
+    // Single parse of the request body; envelope/data stay as borrowed raw JSON
+    let payload = parse_signed_event(&body)?;
+    let envelope = parse_envelope(&payload)?;
+
     // Verify required fields
-    if payload.signature.is_empty() {
-        error!("Missing signature");
-        return Err(StatusCode::BAD_REQUEST);
-    }
-    if payload.payload_hash.is_empty() {
-        error!("Missing payload_hash");
-        return Err(StatusCode::BAD_REQUEST);
-    }
-    if payload.signer_id.is_empty() {
-        error!("Missing signer_id");
-        return Err(StatusCode::BAD_REQUEST);
-    }
+    check_signed_event_fields(&payload)?;
+
+    // The producer embeds its canonical envelope bytes verbatim, so payload_hash is checked against
+    // the SHA-256 of the envelope as received (signature verification below)
+    let envelope_payload_sha256 = Sha256::digest(payload.envelope.get().as_bytes()).to_vec();
 
-    // Note: We trust the payload_hash provided by the agent. JSON serialization
-    // key ordering is non-deterministic when re-serializing JsonValue, so recomputing
-    // the hash here would cause false mismatches. The agent computes the hash from
-    // the canonical envelope struct before converting to JsonValue for transport.
-    // Hash integrity will be verified via signature verification.
-    info!("Received payload_hash={} (trusted from agent)", payload.payload_hash);
+    // Token binding (interim mTLS substitute): the bearer token's agent must be the envelope producer.
+    // Checked before signature verification so unbound producers never reach crypto/DB work.
+    let auth = auth.map(|Extension(a)| a);
+    http_agent_auth::check_envelope_binding(auth.as_ref(), &envelope.component_id, "dpi_probe")?;
 
-    // Verify signature (simplified - in production would verify against trust store)
-    let _sig_bytes = general_purpose::STANDARD.decode(&payload.signature)
+    // Policy rate budget for this component instance (429 + detection, never a silent drop)
+    enforce_component_budget(&budget, store.as_ref(), &dashboard, "dpi_probe", &envelope.component_id).await?;
+
+    // Signature bytes; verified against the signer's key once the agent is resolved
+    let sig_bytes = general_purpose::STANDARD.decode(payload.signature.as_bytes())
         .map_err(|e| {
             error!("Invalid signature base64: {}", e);
-            StatusCode::BAD_REQUEST
+            ValidationError::new(ValidationCategory::InvalidValue, "signature", "base64", format!("invalid base64: {}", e))
         })?;
-    
-    info!("Signature verified OK");
 
     // Extract fields from envelope
-    let message_id = payload.envelope.get("event_id")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| {
-            error!("Missing event_id in envelope");
-            StatusCode::BAD_REQUEST
-        })?;
-    let timestamp_str = payload.envelope.get("timestamp")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| {
-            error!("Missing timestamp in envelope");
-            StatusCode::BAD_REQUEST
-        })?;
-    let timestamp = DateTime::parse_from_rfc3339(timestamp_str)
-        .map_err(|e| {
-            error!("Invalid timestamp format: {}", e);
-            StatusCode::BAD_REQUEST
-        })?
-        .with_timezone(&Utc);
-    let component_id = payload.envelope.get("component_id")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| {
-            error!("Missing component_id in envelope");
-            StatusCode::BAD_REQUEST
-        })?;
-    
-    // Extract data field from envelope
-    let data = payload.envelope.get("data")
-        .ok_or_else(|| {
-            error!("Missing data in envelope");
-            StatusCode::BAD_REQUEST
-        })?;
+    let message_id: &str = &envelope.event_id;
+    let timestamp = parse_envelope_timestamp(&envelope.timestamp)?;
+    let component_id: &str = &envelope.component_id;
 
-    // Parse event data to extract fields
-    let src_ip: Option<String> = data.get("src_ip").and_then(|v| v.as_str()).map(|s| s.to_string());
-    // Parse and validate IP as IpAddr for PostgreSQL INET type
-    let src_ip_param: Option<IpAddr> = src_ip.as_ref()
-        .and_then(|s| s.parse().ok());
-    let src_port = data.get("src_port").and_then(|v| v.as_u64()).map(|v| v as i64);
-    let dst_ip: Option<String> = data.get("dst_ip").and_then(|v| v.as_str()).map(|s| s.to_string());
-    // Parse and validate IP as IpAddr for PostgreSQL INET type
-    let dst_ip_param: Option<IpAddr> = dst_ip.as_ref()
-        .and_then(|s| s.parse().ok());
-    let dst_port = data.get("dst_port").and_then(|v| v.as_u64()).map(|v| v as i64);
-    let protocol = data.get("protocol").and_then(|v| v.as_str()).map(|s| s.to_string());
-    let bytes_in: Option<i64> = None; // Not in current envelope structure
-    let bytes_out: Option<i64> = None; // Not in current envelope structure
-    let packets_in: Option<i64> = None; // Not in current envelope structure
-    let packets_out: Option<i64> = None; // Not in current envelope structure
-    let tls_sni: Option<String> = None; // Not in current envelope structure
-    let http_host: Option<String> = None; // Not in current envelope structure
-    let http_method: Option<String> = None; // Not in current envelope structure
-    let http_path: Option<String> = None; // Not in current envelope structure
-    let iface_name: Option<String> = None; // Not in current envelope structure
-    let flow_id: Option<String> = None; // Not in current envelope structure
-
-    // Get or create agent_id
-    let agent_id = get_or_create_agent(&db, component_id, "dpi_probe").await
-        .map_err(|e| {
-            error!("Failed to get/create agent: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    // Columns from envelope.data through the declarative mapping of this envelope schema version
+    let Some(mapping) = dpi_mapping.for_version(envelope.schema_version) else {
+        error!(
+            "Incompatible DPI envelope schema_version {:?} (mapped versions: {:?})",
+            envelope.schema_version, dpi_mapping.versions()
+        );
+        return Err(ValidationError::new(
+            ValidationCategory::InvalidValue,
+            "envelope.schema_version",
+            &format!("one of {:?}", dpi_mapping.versions()),
+            format!("no DPI field mapping for schema_version {}", envelope.schema_version.unwrap_or(1)),
+        )
+        .into());
+    };
+    let data: serde_json::Value = envelope_validation::from_str("envelope.data", envelope.data.get()).map_err(|e| {
+        error!("Invalid dpi envelope data: {}", e.message);
+        e
+    })?;
+    let fields = mapping.extract(&data);
+    if !fields.rejected.is_empty() {
+        warn!("DPI event {}: values not stored in columns (type mismatch): {:?}", envelope.event_id, fields.rejected);
+    }
+
+    // Resolve agent_id: token-authenticated identity wins; legacy (auth disabled) path auto-registers
+    let agent_id = match &auth {
+        Some(a) => a.agent_id,
+        None => agent_cache.resolve(store.as_ref(), component_id, TelemetrySource::DpiProbe).await
+            .map_err(|e| {
+                error!("Failed to get/create agent: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?,
+    };
+
+    // Region pinning: refuse data this instance's region may not hold (421, audited)
+    let residency_region = enforce_residency(&placement.residency, store.as_ref(), "dpi_probe", agent_id, auth.as_ref(), message_id).await?;
+
+    // Historical identity binding: this agent's events must carry its pinned signing key
+    enforce_key_pin(&signer_keys.pins, store.as_ref(), &dashboard, agent_id, component_id, &payload.signer_id).await?;
 
     // Parse message_id as UUID (using event_id from envelope)
     let message_id_uuid = Uuid::parse_str(message_id)
         .map_err(|e| {
             error!("Invalid message_id UUID format: {}", e);
-            StatusCode::BAD_REQUEST
+            invalid_event_id(e)
         })?;
 
+    // Ed25519 signature of payload_hash against the signer's registered key (403 in enforce mode)
+    let signature_record = enforce_signature(&signer_keys.signatures, store.as_ref(), agent_id, component_id, message_id_uuid, &payload, &sig_bytes).await?;
+
+    // Duplicate agent-id: events of an identity in conflict are held in quarantined_events
+    let origin = IdentityOrigin {
+        source_ip: peer.map(|ConnectInfo(addr)| addr.ip()),
+        host_id: envelope.host_id.as_deref().map(str::to_string),
+    };
+    let screened = screen_identity(&identity_conflicts, store.as_ref(), &dashboard, "dpi_probe", component_id, agent_id, origin, message_id, &body);
+    if let Some(quarantined) = screened.await? {
+        return Ok(quarantined);
+    }
+
+    // Topology: the event carries the probe's zone; both ends of the flow are placed by address
+    let zone = placement.topology.label(component_id);
+    let end = |ip: &Option<String>, port: Option<i32>| Endpoint {
+        addr: ip.as_deref().and_then(|ip| ip.parse().ok()),
+        port: port.and_then(|p| u16::try_from(p).ok()),
+        sensor_zone: None,
+    };
+    let (src, dst) = (end(&fields.src_ip, fields.src_port), end(&fields.dst_ip, fields.dst_port));
+    check_lateral_movement(&placement.topology, store.as_ref(), &dashboard, agent_id, component_id, &src, &dst).await;
+
     // PROMPT-40A: Get ingestion component for audit attribution
-    let ingestion_component_id = get_or_create_ingestion_component(&db).await
+    let ingestion_component_id = store.ingestion_component().await
         .map_err(|e| {
             error!("FAIL-CLOSED: Failed to get/create ingestion component: {}", e);
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
-    // Prepare envelope JSON and hash for audit
-    let full_envelope_json = serde_json::to_value(&payload.envelope)
-        .map_err(|e| {
-            error!("Failed to serialize envelope: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-    let envelope_json_bytes = serde_json::to_vec(&full_envelope_json)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    let mut envelope_hasher = Sha256::new();
-    envelope_hasher.update(&envelope_json_bytes);
-    let envelope_payload_sha256 = envelope_hasher.finalize().to_vec();
-
-    // PROMPT-40A: Start transaction for atomic operations
-    db.execute("BEGIN", &[]).await
+    // Storage policy: envelope.data as received or summary (hashes + flow tuple) for this raw_events row
+    let payload_facts = PayloadFacts {
+        source: TelemetrySource::DpiProbe,
+        event_category: None,
+        event_type: None,
+    };
+    let payload_decision = payload_policy.decide(payload_facts, &envelope_payload_sha256);
+    let priority = ShedPriority::classify(payload_facts.source, payload_facts.event_category, payload_facts.event_type);
+    let raw_payload_json = match payload_decision.storage {
+        PayloadStorage::Full => envelope.data.to_owned(),
+        PayloadStorage::Summary => payload_policy::summary_payload(
+            &payload_decision,
+            payload_facts,
+            &envelope,
+            &envelope_payload_sha256,
+            &Sha256::digest(envelope.data.get().as_bytes()),
+            serde_json::json!({
+                "src_ip": fields.src_ip,
+                "src_port": fields.src_port,
+                "dst_ip": fields.dst_ip,
+                "dst_port": fields.dst_port,
+                "protocol": fields.protocol,
+            }),
+        )
         .map_err(|e| {
-            error!("Failed to start transaction: {}", e);
+            error!("Failed to build raw_events summary payload: {}", e);
             StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+        })?,
+    };
 
-    // PROMPT-40A: Audit INGEST_ACCEPT (after signature verification + agent resolution)
-    let ingest_accept_payload = serde_json::json!({
-        "message_id": message_id,
-        "signer_id": payload.signer_id,
-        "payload_hash": payload.payload_hash,
-        "source": "dpi_probe",
-        "agent_id": agent_id.to_string(),
-        "envelope_keys": payload.envelope.as_object().map(|o| o.keys().collect::<Vec<_>>()).unwrap_or_default()
+    let telemetry = TelemetryRecord::Dpi(DpiTelemetry {
+        provenance: TelemetryProvenance {
+            agent_id,
+            message_id: message_id_uuid,
+            nonce: Uuid::new_v4().to_string(),
+            component_identity: component_id.to_string(),
+            signature_b64: payload.signature.to_string(),
+            signature_alg: "RSA-PSS-SHA256".to_string(),
+            data_hash_hex: payload.payload_hash.to_string(),
+            observed_at: timestamp,
+        },
+        // Only addresses that parsed as IpAddr reach the INET columns
+        src_ip: fields.src_ip,
+        src_port: fields.src_port,
+        dst_ip: fields.dst_ip,
+        dst_port: fields.dst_port,
+        protocol: fields.protocol,
+        bytes_in: fields.bytes_in,
+        bytes_out: fields.bytes_out,
+        packets_in: fields.packets_in,
+        packets_out: fields.packets_out,
+        packet_count: fields.packet_count,
+        byte_count: fields.byte_count,
+        tls_sni: fields.tls_sni,
+        ja3: fields.ja3,
+        ja3s: fields.ja3s,
+        http_host: fields.http_host,
+        http_method: fields.http_method,
+        http_path: fields.http_path,
+        iface_name: fields.iface_name,
+        flow_id: fields.flow_id,
+        metadata_json: fields.metadata.map(|m| serde_json::Value::Object(m).to_string()),
+        payload_json: envelope.data.get().to_string(),
+        payload_sha256: Some(hex::decode(payload.payload_hash.as_bytes()).unwrap_or_default()),
     });
-    let ingest_accept_payload_str = serde_json::to_string(&ingest_accept_payload)
-        .map_err(|e| {
-            error!("Failed to serialize ingest accept audit payload: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-    let mut ingest_accept_hasher = Sha256::new();
-    ingest_accept_hasher.update(ingest_accept_payload_str.as_bytes());
-    let ingest_accept_payload_sha256 = ingest_accept_hasher.finalize().to_vec();
-    
-    insert_immutable_audit_log(
-        &db,
-        Some(ingestion_component_id),
-        Some(agent_id),
+
+    // PROMPT-40A: Audit INGEST_ACCEPT (after signature verification + agent resolution)
+    let ingest_accept_audit = ingest_audit_record(
+        ingestion_component_id,
+        agent_id,
         "INGEST_ACCEPT",
-        "raw_event",
         None,
-        Some(timestamp),
-        &ingest_accept_payload,
-        &ingest_accept_payload_sha256,
-    ).await.map_err(|e| {
-        error!("FAIL-CLOSED: Failed to insert INGEST_ACCEPT audit log: {}", e);
+        timestamp,
+        serde_json::json!({
+            "message_id": message_id,
+            "signer_id": payload.signer_id,
+            "payload_hash": payload.payload_hash,
+            "source": "dpi_probe",
+            "agent_id": agent_id.to_string(),
+            "envelope_sha256": hex::encode(&envelope_payload_sha256),
+            "residency_region": residency_region
+        }),
+    ).map_err(|e| {
+        error!("Failed to serialize ingest accept audit payload: {}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
-    // Convert IpAddr to String for PostgreSQL INET binding (validated as IpAddr above)
-    let src_ip_str: Option<String> = src_ip_param.as_ref().map(|ip| ip.to_string());
-    let dst_ip_str: Option<String> = dst_ip_param.as_ref().map(|ip| ip.to_string());
-
-    // Materialize all parameters as named variables to ensure proper lifetimes
-    let dpi_nonce = Uuid::new_v4().to_string();
-    let dpi_signature_alg = Some("RSA-PSS-SHA256".to_string());
-    let src_ip_param_str: Option<&str> = src_ip_str.as_deref();
-    let src_port_param: Option<i32> = src_port.map(|v| v as i32);
-    let dst_ip_param_str: Option<&str> = dst_ip_str.as_deref();
-    let dst_port_param: Option<i32> = dst_port.map(|v| v as i32);
-    let protocol_param: Option<&str> = protocol.as_deref();
-    let tls_sni_param: Option<&str> = tls_sni.as_deref();
-    let http_host_param: Option<&str> = http_host.as_deref();
-    let http_method_param: Option<&str> = http_method.as_deref();
-    let http_path_param: Option<&str> = http_path.as_deref();
-    let iface_name_param: Option<&str> = iface_name.as_deref();
-    let flow_id_param: Option<&str> = flow_id.as_deref();
-    let dpi_payload_json = serde_json::to_string(data).unwrap_or_else(|_| "{}".to_string());
-    let dpi_payload_sha256 = Some(hex::decode(&payload.payload_hash).unwrap_or_default());
-
-    // Insert into raw_events for DPI (within transaction)
-    let raw_event_id = match db.query_one(
-        r#"
-        INSERT INTO raw_events (
-            source_type, source_agent_id, observed_at, received_at,
-            event_name, payload_json, payload_sha256
-        )
-        VALUES ('dpi_probe'::event_source_type, $1, $2, NOW(), $3, $4, $5)
-        RETURNING raw_event_id
-        "#,
-        &[
-            &agent_id,
-            &timestamp,
-            &"flow",
-            &data,
-            &envelope_payload_sha256,
-        ],
-    ).await {
-        Ok(row) => {
-            let raw_event_id: Uuid = row.get(0);
-            info!("raw_events inserted for DPI | raw_event_id={} | agent_id={} | message_id={}", raw_event_id, agent_id, message_id);
-            raw_event_id
+    // Database degradation: shed low-priority events and slow agents, never critical events
+    let admission = load_shedder.admit(priority, &pipeline.load(agent_id));
+    if admission == Admission::Reject {
+        return Err(StatusCode::SERVICE_UNAVAILABLE.into());
+    }
+
+    // Persist on the agent's pipeline shard: an agent's events commit in the order they were accepted;
+    // replayed backlog waits in its own lane so fresh events are not stuck behind it
+    let shard_message_id = message_id.to_string();
+    let raw_event_id = pipeline
+        .run_event(agent_id, timestamp, move |store| async move {
+            let message_id = shard_message_id.as_str();
+
+            // PROMPT-40A: One unit of work for atomic operations
+            let tx_span = info_span!("ingest.db_transaction", source = "dpi_probe");
+            let mut tx = store.begin().instrument(tx_span.clone()).await
+                .map_err(|e| {
+                    error!("Failed to start transaction: {}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+
+            // A shed event is acknowledged once it is in the WAL, before the flush
+            if admission == Admission::Relaxed {
+                if let Err(e) = tx.relax_durability().instrument(tx_span.clone()).await {
+                    return Err(abort_tx(tx, "Failed to relax commit durability", e).instrument(tx_span).await);
+                }
+            }
+
+            if let Err(e) = tx.append_audit(&ingest_accept_audit).instrument(tx_span.clone()).await {
+                return Err(abort_tx(tx, "Failed to insert INGEST_ACCEPT audit log", e).instrument(tx_span).await);
+            }
+
+            if let Some(record) = &signature_record {
+                if let Err(e) = tx.insert_signature_validation(ingestion_component_id, record).instrument(tx_span.clone()).await {
+                    return Err(abort_tx(tx, "Failed to insert signature_validation_events", e).instrument(tx_span).await);
+                }
+            }
+
+            // Insert into raw_events for DPI (within transaction)
+            let raw_event = RawEventRecord {
+                source: TelemetrySource::DpiProbe,
+                agent_id,
+                observed_at: timestamp,
+                event_name: "flow".to_string(),
+                payload_json: raw_payload_json,
+                payload_sha256: envelope_payload_sha256.clone(),
+                payload_storage: payload_decision.storage,
+                payload_storage_reason: payload_decision.reason.clone(),
+                residency_region: residency_region.clone(),
+                zone: zone.clone(),
+            };
+            let raw_event_id = match tx.insert_raw(&raw_event).instrument(tx_span.clone()).await {
+                Ok(raw_event_id) => {
+                    info!("raw_events inserted for DPI | raw_event_id={} | agent_id={} | message_id={} | payload_storage={}", raw_event_id, agent_id, message_id, payload_decision.storage.as_str());
+                    raw_event_id
+                }
+                Err(e) => return Err(abort_tx(tx, "Failed to insert raw_events for DPI", e).instrument(tx_span).await),
+            };
+
+            // PROMPT-40A: Audit RAW_EVENT_INSERT (after successful raw_events INSERT, same transaction)
+            let raw_event_insert_audit = match ingest_audit_record(
+                ingestion_component_id,
+                agent_id,
+                "RAW_EVENT_INSERT",
+                Some(raw_event_id),
+                timestamp,
+                serde_json::json!({
+                    "raw_event_id": raw_event_id.to_string(),
+                    "source_type": "dpi_probe",
+                    "agent_id": agent_id.to_string(),
+                    "residency_region": residency_region,
+                    "event_name": "flow",
+                    "observed_at": timestamp.to_rfc3339(),
+                    "payload_sha256": hex::encode(&envelope_payload_sha256),
+                    "payload_storage": payload_decision.storage.as_str(),
+                    "payload_storage_reason": payload_decision.reason
+                }),
+            ) {
+                Ok(record) => record,
+                Err(e) => return Err(abort_tx(tx, "Failed to serialize raw event insert audit payload", e).instrument(tx_span).await),
+            };
+            if let Err(e) = tx.append_audit(&raw_event_insert_audit).instrument(tx_span.clone()).await {
+                return Err(abort_tx(tx, "Failed to insert RAW_EVENT_INSERT audit log", e).instrument(tx_span).await);
+            }
+
+            // Insert into dpi_probe_telemetry
+            if let Err(e) = tx.insert_telemetry(&telemetry).instrument(tx_span.clone()).await {
+                return Err(abort_tx(tx, "Failed to insert dpi_probe_telemetry", e).instrument(tx_span).await);
+            }
+
+            // Outbox message commits (or rolls back) with the event
+            if outbox.enabled() {
+                let record = outbox::telemetry_accepted(
+                    raw_event_id, "dpi_probe", agent_id, message_id, "flow", timestamp, residency_region.as_deref(), zone.as_ref(),
+                );
+                if let Err(e) = tx.enqueue_outbox(&record).instrument(tx_span.clone()).await {
+                    return Err(abort_tx(tx, "Failed to insert ingest_outbox message", e).instrument(tx_span).await);
+                }
+            }
+
+            // Commit transaction (raw_events + telemetry + audit persisted atomically)
+            tx.commit().instrument(tx_span).await
+                .map_err(|e| {
+                    error!("FAIL-CLOSED: Failed to commit transaction: {}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+
+            Ok::<Uuid, StatusCode>(raw_event_id)
+        })
+        .await
+        .map_err(pipeline_status)??;
+
+    info!("Ingested dpi event {} | Persisted raw_event_id={}", message_id, raw_event_id);
+
+    Ok(Json(IngestResponse {
+        status: "ok".to_string(),
+        message_id: message_id.to_string(),
+    }))
+}
+
+/// Status of an ingest request whose unit of work the pipeline did not run: a full shard queue is
+/// transient (503, the agent retries), a missing or failed worker is a server error.
+fn pipeline_status(e: PipelineError) -> StatusCode {
+    match e {
+        PipelineError::Busy => StatusCode::SERVICE_UNAVAILABLE,
+        PipelineError::Unavailable => {
+            error!("FAIL-CLOSED: ingest pipeline shard unavailable");
+            StatusCode::INTERNAL_SERVER_ERROR
         }
-        Err(e) => {
-            error!("FAIL-CLOSED: Failed to insert raw_events for DPI: {}", e);
-            let _ = db.execute("ROLLBACK", &[]).await;
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
+
+/// Parse the request body once into the borrowed wire view (400 with the failing field on
+/// malformed JSON or missing fields).
+fn parse_signed_event(body: &[u8]) -> Result<SignedEventRef<'_>, ValidationError> {
+    let payload: SignedEventRef<'_> = envelope_validation::from_slice("", body).map_err(|e| {
+        error!("VALIDATION ERROR: Invalid signed event: {} | field={}", e.message, e.field);
+        e
+    })?;
+    tracing::Span::current().record("signer_id", payload.signer_id.as_ref());
+    Ok(payload)
+}
+
+fn parse_envelope<'a>(payload: &SignedEventRef<'a>) -> Result<EnvelopeRef<'a>, ValidationError> {
+    envelope_validation::from_str("envelope", payload.envelope.get()).map_err(|e| {
+        error!("VALIDATION ERROR: Invalid envelope: {} | field={}", e.message, e.field);
+        e
+    })
+}
+
+/// Header fields that must be present and non-empty.
+fn check_signed_event_fields(payload: &SignedEventRef<'_>) -> Result<(), ValidationError> {
+    for (field, value) in [
+        ("signature", &payload.signature),
+        ("payload_hash", &payload.payload_hash),
+        ("signer_id", &payload.signer_id),
+    ] {
+        if value.is_empty() {
+            error!("VALIDATION ERROR: Missing {} field", field);
+            return Err(ValidationError::empty(field));
         }
-    };
+    }
+    Ok(())
+}
 
-    // PROMPT-40A: Audit RAW_EVENT_INSERT (after successful raw_events INSERT, same transaction)
-    let raw_event_insert_payload = serde_json::json!({
-        "raw_event_id": raw_event_id.to_string(),
-        "source_type": "dpi_probe",
-        "agent_id": agent_id.to_string(),
-        "event_name": "flow",
-        "observed_at": timestamp.to_rfc3339(),
-        "payload_sha256": hex::encode(&envelope_payload_sha256)
-    });
-    let raw_event_insert_payload_str = serde_json::to_string(&raw_event_insert_payload)
+fn parse_envelope_timestamp(value: &str) -> Result<DateTime<Utc>, ValidationError> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&Utc))
         .map_err(|e| {
-            error!("Failed to serialize raw event insert audit payload: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-    let mut raw_event_insert_hasher = Sha256::new();
-    raw_event_insert_hasher.update(raw_event_insert_payload_str.as_bytes());
-    let raw_event_insert_payload_sha256 = raw_event_insert_hasher.finalize().to_vec();
-    
-    insert_immutable_audit_log(
-        &db,
-        Some(ingestion_component_id),
-        Some(agent_id),
-        "RAW_EVENT_INSERT",
-        "raw_event",
-        Some(raw_event_id),
-        Some(timestamp),
-        &raw_event_insert_payload,
-        &raw_event_insert_payload_sha256,
-    ).await.map_err(|e| {
-        error!("FAIL-CLOSED: Failed to insert RAW_EVENT_INSERT audit log: {}", e);
+            error!("Invalid timestamp format: {}", e);
+            ValidationError::new(
+                ValidationCategory::InvalidValue,
+                "envelope.timestamp",
+                "RFC 3339 timestamp",
+                format!("'{}' is not an RFC 3339 timestamp: {}", value, e),
+            )
+        })
+}
+
+fn invalid_event_id(e: uuid::Error) -> ValidationError {
+    ValidationError::new(ValidationCategory::InvalidValue, "envelope.event_id", "UUID", format!("event_id is not a UUID: {}", e))
+}
+
+/// PROMPT-40A: Ingestion audit entry; payload_sha256 covers the serialized payload JSON.
+fn ingest_audit_record(
+    ingestion_component_id: Uuid,
+    agent_id: Uuid,
+    action: &str,
+    object_id: Option<Uuid>,
+    event_time: DateTime<Utc>,
+    payload: JsonValue,
+) -> Result<AuditRecord, serde_json::Error> {
+    let payload_str = serde_json::to_string(&payload)?;
+    let mut hasher = Sha256::new();
+    hasher.update(payload_str.as_bytes());
+    Ok(AuditRecord {
+        actor_component_id: Some(ingestion_component_id),
+        actor_agent_id: Some(agent_id),
+        action: action.to_string(),
+        object_type: "raw_event".to_string(),
+        object_id,
+        event_time: Some(event_time),
+        payload_json: payload,
+        payload_sha256: hasher.finalize().to_vec(),
+    })
+}
+
+/// Count the event against its component's policy budget. Over budget -> 429 (the agent delivery
+/// layer retries with backoff); the first rejection of each window is recorded as a detection.
+async fn enforce_component_budget(
+    budget: &ComponentRateBudget,
+    store: &dyn TelemetryStore,
+    dashboard: &DashboardCache,
+    component_type: &str,
+    component_id: &str,
+) -> Result<(), StatusCode> {
+    let decision = budget.decide(component_type, component_id).await.map_err(|e| {
+        error!("FAIL-CLOSED: ingest rate budget unavailable: {}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
+    let violation = match decision {
+        BudgetDecision::Allowed => return Ok(()),
+        BudgetDecision::Exceeded(v) => v,
+    };
+    if violation.first_in_window {
+        warn!(
+            "Ingest rate budget exceeded | component_type={} | component_id={} | events_per_minute={} | policy={} v{}",
+            component_type, component_id, violation.quota.events_per_minute, violation.quota.policy_id, violation.quota.policy_version
+        );
+        if let Err(e) = record_budget_violation(store, dashboard, &violation).await {
+            error!("Failed to record ingest rate budget detection: {}", e);
+        }
+    }
+    Err(StatusCode::TOO_MANY_REQUESTS)
+}
 
-    // Insert into dpi_probe_telemetry
-    let result = db.execute(
-        r#"
-        INSERT INTO dpi_probe_telemetry (
-            agent_id, source_message_id, source_nonce, source_component_identity,
-            source_signature_b64, source_signature_alg, source_data_hash_hex,
-            observed_at, src_ip, src_port, dst_ip, dst_port, protocol,
-            bytes_in, bytes_out, packets_in, packets_out, tls_sni,
-            http_host, http_method, http_path, iface_name, flow_id, payload, payload_sha256
-        )
-        VALUES (
-            $1, $2, $3, $4, $5, $6, $7, $8, $9::inet, $10, $11::inet, $12, $13, $14, $15, $16, $17,
-            $18, $19, $20, $21, $22, $23, $24::jsonb, $25
+/// One detection_results row (+ audit entry) per component per violation window.
+async fn record_budget_violation(
+    store: &dyn TelemetryStore,
+    dashboard: &DashboardCache,
+    violation: &BudgetViolation,
+) -> Result<Uuid, String> {
+    let quota = &violation.quota;
+    let window_start = violation.window_start.to_rfc3339();
+    let artifacts = serde_json::json!({
+        "component_type": quota.component_type,
+        "component_id": violation.component_id,
+        "events_per_minute": quota.events_per_minute,
+        "policy_id": quota.policy_id,
+        "policy_version": quota.policy_version,
+        "window_start": window_start,
+    });
+    let detection = DetectionRecord {
+        detection_engine: "ingest_rate_budget".to_string(),
+        detection_name: "ingest_rate_budget_exceeded".to_string(),
+        detection_category: Some("sensor_abuse".to_string()),
+        severity: "warning".to_string(),
+        confidence: 1.0,
+        reasoning: format!(
+            "{} {} exceeded its policy ingest budget of {} events/minute (policy {} v{})",
+            quota.component_type, violation.component_id, quota.events_per_minute, quota.policy_id, quota.policy_version
+        ),
+        artifacts,
+        deterministic_key: Sha256::digest(
+            format!("{}|{}|{}", quota.component_type, violation.component_id, window_start).as_bytes(),
         )
-        "#,
-        &[
-            &agent_id,
-            &message_id_uuid,
-            &dpi_nonce,
-            &component_id,
-            &payload.signature,
-            &dpi_signature_alg,
-            &payload.payload_hash,
-            &timestamp,
-            &src_ip_param_str,
-            &src_port_param,
-            &dst_ip_param_str,
-            &dst_port_param,
-            &protocol_param,
-            &bytes_in,
-            &bytes_out,
-            &packets_in,
-            &packets_out,
-            &tls_sni_param,
-            &http_host_param,
-            &http_method_param,
-            &http_path_param,
-            &iface_name_param,
-            &flow_id_param,
-            &dpi_payload_json,
-            &dpi_payload_sha256,
-        ],
-    ).await;
-
-    match result {
-        Ok(_) => {
-            info!("Ingested dpi event {} | Persisted raw_event_id={}", message_id, message_id_uuid);
-            
-            // Commit transaction (raw_events + telemetry + audit persisted atomically)
-            db.execute("COMMIT", &[]).await
-                .map_err(|e| {
-                    error!("FAIL-CLOSED: Failed to commit transaction: {}", e);
-                    StatusCode::INTERNAL_SERVER_ERROR
-                })?;
-            
-            Ok(Json(IngestResponse {
-                status: "ok".to_string(),
-                message_id: message_id.to_string(),
-            }))
+        .to_vec(),
+    };
+    record_ingest_detection(store, dashboard, &detection, None, "INGEST_QUOTA_VIOLATION").await
+}
+
+/// Apply the residency policy to the authenticated agent's region. Returns the residency
+/// classification recorded on raw_events; a refused event is audited and rejected with 421 so the
+/// agent redirects delivery to an instance in its own region.
+async fn enforce_residency(
+    residency: &ResidencyPolicy,
+    store: &dyn TelemetryStore,
+    component_type: &str,
+    agent_id: Uuid,
+    auth: Option<&AuthenticatedAgent>,
+    message_id: &str,
+) -> Result<Option<String>, StatusCode> {
+    let agent_region = auth.and_then(|a| a.region.as_deref());
+    match residency.decide(agent_region) {
+        ResidencyDecision::Accepted { region, cross_region } => {
+            if cross_region {
+                info!(
+                    "Cross-region delivery allowed by policy | agent_id={} | agent_region={:?} | local_region={:?}",
+                    agent_id, region, residency.local_region
+                );
+            }
+            Ok(region)
         }
-        Err(e) => {
-            error!("Failed to insert dpi_probe_telemetry: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        ResidencyDecision::Refused { agent_region, reason } => {
+            warn!("RESIDENCY REJECT: {} | agent_id={} | message_id={}", reason, agent_id, message_id);
+            http_agent_auth::audit(
+                store,
+                Some(agent_id),
+                "INGEST_RESIDENCY_REJECT",
+                None,
+                &serde_json::json!({
+                    "agent_id": agent_id.to_string(),
+                    "component_type": component_type,
+                    "message_id": message_id,
+                    "agent_region": agent_region,
+                    "local_region": residency.local_region,
+                    "reason": reason,
+                }),
+            )
+            .await?;
+            Err(StatusCode::MISDIRECTED_REQUEST)
         }
     }
 }
 
-async fn get_or_create_agent(
-    db: &Client,
-    component_identity: &str,
-    agent_type: &str,
-) -> Result<Uuid, Box<dyn std::error::Error>> {
-    // Log parameter types and values for debugging
-    error!("get_or_create_agent called | component_identity type={} value={} | agent_type type={} value={}", 
-        std::any::type_name::<&str>(), component_identity,
-        std::any::type_name::<&str>(), agent_type);
-    
-    // Validate agent_type is a valid enum value
-    let valid_types = ["linux_agent", "windows_agent", "dpi_probe", "core_engine", "ai_core", "alert_engine", "policy_engine", "correlation_engine", "llm", "response_engine", "forensic_engine", "unknown"];
-    if !valid_types.contains(&agent_type) {
-        let err_msg = format!("Invalid agent_type: {} (must be one of: {:?})", agent_type, valid_types);
-        error!("{}", err_msg);
-        return Err(Box::new(std::io::Error::new(std::io::ErrorKind::InvalidInput, err_msg)));
+/// Verify the event's signature against the signer's registered key, over a payload_hash that must
+/// match the received envelope. Returns the result to record with the event (None when
+/// verification is disabled); a failure in enforce mode is recorded on its own and rejected with 403.
+async fn enforce_signature(
+    signatures: &EventSignatureVerifier,
+    store: &dyn TelemetryStore,
+    agent_id: Uuid,
+    component_id: &str,
+    message_id: Uuid,
+    payload: &SignedEventRef<'_>,
+    signature: &[u8],
+) -> Result<Option<SignatureValidationRecord>, StatusCode> {
+    if signatures.mode == SignatureMode::Disabled {
+        return Ok(None);
     }
-    
-    // Try to find existing agent by host_hostname (using component_identity as identifier)
-    // Note: agent_type is validated above, so we can safely inject it into SQL
-    // We parameterize component_identity to prevent SQL injection
-    let query = format!(
-        r#"
-        SELECT agent_id FROM agents
-        WHERE host_hostname = $1 AND agent_type = '{}'::event_source_type
-        LIMIT 1
-        "#,
-        agent_type.replace("'", "''") // Escape single quotes for SQL safety
+    let check = signatures
+        .verify(agent_id, &payload.signer_id, payload.sequence, &payload.payload_hash, payload.envelope.get().as_bytes(), signature)
+        .instrument(info_span!("ingest.signature_verify", signer_id = %payload.signer_id))
+        .await
+        .map_err(|e| {
+            error!("FAIL-CLOSED: Event signature verification failed: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    let record = SignatureValidationRecord {
+        object_id: message_id,
+        signature_b64: payload.signature.to_string(),
+        status: check.status.as_str().to_string(),
+        signer_identity: payload.signer_id.to_string(),
+        context: serde_json::json!({
+            "agent_id": agent_id.to_string(),
+            "key_source": check.key_source.map(|s| s.as_str()),
+            "sequence": payload.sequence,
+            "mode": signatures.mode.as_str(),
+        }),
+        error_details: check.error.clone(),
+    };
+    if check.is_valid() {
+        return Ok(Some(record));
+    }
+    warn!(
+        "Event signature {} | agent_id={} | component_id={} | signer_id={} | message_id={} | error={} | mode={}",
+        check.status.as_str(), agent_id, component_id, payload.signer_id, message_id,
+        check.error.as_deref().unwrap_or(""), signatures.mode.as_str()
     );
-    
-    let row = db.query_opt(
-        &query,
-        &[&component_identity],
-    ).await.map_err(|e| {
-        // Log full error chain
-        let error_chain = format!("{:?}", e);
-        error!("Database query error in get_or_create_agent | component_identity={} (Rust type: &str, value: {}) | agent_type={} (Rust type: &str, value: {}) | error={} | error_chain={}", 
-            component_identity, component_identity, agent_type, agent_type, e, error_chain);
-        
-        // Check if it's a type mismatch error
-        let error_str = format!("{}", e);
-        if error_str.contains("serializing") {
-            error!("SERIALIZATION ERROR DETAILS: Parameter 1 (component_identity) is &str -> should map to TEXT column host_hostname | Parameter 2 (agent_type) is &str -> should map to event_source_type ENUM via CAST");
+    if signatures.mode == SignatureMode::Enforce {
+        if let Err(e) = record_signature_rejection(store, &record).await {
+            error!("Failed to record rejected event signature: {}", e);
         }
-        e
-    })?;
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(Some(record))
+}
 
-    if let Some(r) = row {
-        // Update last_seen_at
-        let agent_id: Uuid = r.get(0);
-        error!("Found existing agent | agent_id={}", agent_id);
-        db.execute(
-            r#"UPDATE agents SET last_seen_at = NOW() WHERE agent_id = $1"#,
-            &[&agent_id],
-        ).await.map_err(|e| {
-            error!("Failed to update last_seen_at | agent_id={} | error={}", agent_id, e);
-            e
-        })?;
-        return Ok(agent_id);
+/// signature_validation_events row of a rejected event, in its own unit of work.
+async fn record_signature_rejection(store: &dyn TelemetryStore, record: &SignatureValidationRecord) -> Result<Uuid, StorageError> {
+    let validator = store.ingestion_component().await?;
+    let mut tx = store.begin().await?;
+    match tx.insert_signature_validation(validator, record).await {
+        Ok(id) => {
+            tx.commit().await?;
+            Ok(id)
+        }
+        Err(e) => {
+            tx.rollback().await;
+            Err(e)
+        }
     }
+}
 
-    // Create new agent
-    error!("No existing agent found, creating new agent | component_identity={} | agent_type={}", 
-        component_identity, agent_type);
-    let agent_id = Uuid::new_v4();
-    
-    // Note: agent_type is validated above, so we can safely inject it into SQL
-    let insert_query = format!(
-        r#"
-        INSERT INTO agents (agent_id, agent_type, host_hostname, first_seen_at, last_seen_at, is_active)
-        VALUES ($1, '{}'::event_source_type, $2, NOW(), NOW(), true)
-        "#,
-        agent_type.replace("'", "''") // Escape single quotes for SQL safety
-    );
-    
-    db.execute(
-        &insert_query,
-        &[&agent_id, &component_identity],
-    ).await.map_err(|e| {
-        let error_chain = format!("{:?}", e);
-        error!("Database INSERT error in get_or_create_agent | agent_id={} (Rust type: Uuid) | agent_type={} (Rust type: &str, value: {}) | component_identity={} (Rust type: &str, value: {}) | error={} | error_chain={}", 
-            agent_id, agent_type, agent_type, component_identity, component_identity, e, error_chain);
-        
-        let error_str = format!("{}", e);
-        if error_str.contains("serializing") {
-            error!("SERIALIZATION ERROR DETAILS: Parameter 1 (agent_id) is Uuid -> should map to UUID column | Parameter 2 (agent_type) is &str -> should map to event_source_type ENUM via CAST | Parameter 3 (component_identity) is &str -> should map to TEXT column host_hostname");
+/// Check the presented signer_id against the agent's pinned key. A mismatch is recorded once per
+/// (agent, key) as a detection; enforce mode rejects the event with 403.
+async fn enforce_key_pin(
+    key_pins: &KeyPinning,
+    store: &dyn TelemetryStore,
+    dashboard: &DashboardCache,
+    agent_id: Uuid,
+    component_id: &str,
+    signer_id: &str,
+) -> Result<(), StatusCode> {
+    let mismatch = match key_pins.check(agent_id, signer_id).await {
+        Ok(PinDecision::Accepted) | Ok(PinDecision::PinnedFirstUse) => return Ok(()),
+        Ok(PinDecision::Mismatch(m)) => m,
+        Err(e) => {
+            error!("FAIL-CLOSED: Agent key pin check failed: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
         }
-        e
-    })?;
+    };
+    if mismatch.first_report {
+        warn!(
+            "Unexpected signing key | agent_id={} | component_id={} | pinned={} | presented={} | mode={}",
+            agent_id, component_id, mismatch.pinned_signer_id, mismatch.presented_signer_id, key_pins.mode.as_str()
+        );
+        if let Err(e) = record_key_mismatch(store, dashboard, component_id, &mismatch).await {
+            error!("Failed to record agent key mismatch detection: {}", e);
+        }
+    }
+    if key_pins.mode == KeyPinMode::Enforce {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(())
+}
 
-    error!("Successfully created agent | agent_id={} | component_identity={} | agent_type={}", 
-        agent_id, component_identity, agent_type);
-    Ok(agent_id)
+async fn record_key_mismatch(
+    store: &dyn TelemetryStore,
+    dashboard: &DashboardCache,
+    component_id: &str,
+    mismatch: &KeyMismatch,
+) -> Result<Uuid, String> {
+    let artifacts = serde_json::json!({
+        "agent_id": mismatch.agent_id.to_string(),
+        "component_id": component_id,
+        "pinned_signer_id": mismatch.pinned_signer_id,
+        "presented_signer_id": mismatch.presented_signer_id,
+    });
+    let detection = DetectionRecord {
+        detection_engine: "ingest_key_pinning".to_string(),
+        detection_name: "agent_unexpected_signing_key".to_string(),
+        detection_category: Some("identity_spoofing".to_string()),
+        severity: "critical".to_string(),
+        confidence: 1.0,
+        reasoning: format!(
+            "Agent {} presented signing key '{}' but is pinned to '{}' with no approved rotation",
+            component_id, mismatch.presented_signer_id, mismatch.pinned_signer_id
+        ),
+        artifacts,
+        deterministic_key: Sha256::digest(
+            format!("{}|{}|{}", mismatch.agent_id, mismatch.pinned_signer_id, mismatch.presented_signer_id).as_bytes(),
+        )
+        .to_vec(),
+    };
+    record_ingest_detection(store, dashboard, &detection, Some(mismatch.agent_id), "AGENT_KEY_MISMATCH").await
 }
 
-// PROMPT-40A: Get or create ingestion component for audit attribution
-async fn get_or_create_ingestion_component(
-    db: &Client,
-) -> Result<Uuid, Box<dyn std::error::Error>> {
-    let component_name = "ransomeye_ingestion";
-    let instance_id = hostname::get()
-        .unwrap_or_default()
-        .to_string_lossy()
-        .to_string();
-    
-    // Try to find existing component
-    let row = db.query_opt(
-        r#"
-        SELECT component_id FROM components
-        WHERE component_type = 'core_engine'::component_type
-          AND component_name = $1
-          AND (instance_id = $2 OR (instance_id IS NULL AND $2 IS NULL))
-        LIMIT 1
-        "#,
-        &[&component_name, &instance_id],
-    ).await?;
-
-    if let Some(r) = row {
-        let component_id: Uuid = r.get(0);
-        // Update last_heartbeat_at
-        db.execute(
-            r#"UPDATE components SET last_heartbeat_at = NOW() WHERE component_id = $1"#,
-            &[&component_id],
-        ).await?;
-        return Ok(component_id);
+/// Record lineage findings as detections. Findings never reject the event (it is the evidence);
+/// a detection that cannot be recorded is logged, as for key mismatches.
+async fn verify_lineage(
+    verifier: &LineageVerifier,
+    store: &dyn TelemetryStore,
+    dashboard: &DashboardCache,
+    agent_id: Uuid,
+    component_id: &str,
+    event: &LineageEvent<'_>,
+    link: &LinuxLineage,
+) {
+    for finding in verifier.observe(agent_id, event, link) {
+        warn!(
+            "Process lineage violation | kind={} | agent_id={} | component_id={} | boot_id={} | pid={}",
+            finding.kind(), agent_id, component_id, link.boot_id, event.pid
+        );
+        if let Err(e) = record_lineage_finding(store, dashboard, agent_id, component_id, &finding).await {
+            error!("Failed to record process lineage detection: {}", e);
+        }
     }
+}
+
+async fn record_lineage_finding(
+    store: &dyn TelemetryStore,
+    dashboard: &DashboardCache,
+    agent_id: Uuid,
+    component_id: &str,
+    finding: &LineageFinding,
+) -> Result<Uuid, String> {
+    // Tampering is proof; a broken link may also be an event the agent shed under overload
+    let (detection_name, severity, reasoning, artifacts, key_hash) = match finding {
+        LineageFinding::TamperedEvent { boot_id, chain_id, hash, expected_hash } => (
+            "process_lineage_tampered_event",
+            "critical",
+            format!(
+                "Process event from {} carries lineage hash {} but its fields hash to {}; the event was altered or fabricated",
+                component_id, hash, expected_hash
+            ),
+            serde_json::json!({
+                "boot_id": boot_id,
+                "chain_id": chain_id,
+                "hash": hash,
+                "expected_hash": expected_hash,
+            }),
+            hash,
+        ),
+        LineageFinding::BrokenLink { boot_id, chain_id, parent_hash, child_hash } => (
+            "process_lineage_broken_link",
+            "error",
+            format!(
+                "Process event {} from {} links to parent {} which never arrived; a process event is missing or this one was injected",
+                child_hash, component_id, parent_hash
+            ),
+            serde_json::json!({
+                "boot_id": boot_id,
+                "chain_id": chain_id,
+                "parent_hash": parent_hash,
+                "child_hash": child_hash,
+            }),
+            child_hash,
+        ),
+    };
+    let mut artifacts = artifacts;
+    artifacts["agent_id"] = serde_json::json!(agent_id.to_string());
+    artifacts["component_id"] = serde_json::json!(component_id);
+    let detection = DetectionRecord {
+        detection_engine: "ingest_process_lineage".to_string(),
+        detection_name: detection_name.to_string(),
+        detection_category: Some("telemetry_tampering".to_string()),
+        severity: severity.to_string(),
+        confidence: 1.0,
+        reasoning,
+        artifacts,
+        deterministic_key: Sha256::digest(format!("{}|{}|{}", agent_id, finding.kind(), key_hash).as_bytes()).to_vec(),
+    };
+    record_ingest_detection(store, dashboard, &detection, Some(agent_id), "PROCESS_LINEAGE_VIOLATION").await
+}
 
-    // Create new component
-    let component_id = Uuid::new_v4();
-    db.execute(
-        r#"
-        INSERT INTO components (component_id, component_type, component_name, instance_id, started_at, last_heartbeat_at)
-        VALUES ($1, 'core_engine'::component_type, $2, $3, NOW(), NOW())
-        "#,
-        &[&component_id, &component_name, &instance_id],
-    ).await?;
-
-    Ok(component_id)
+/// Report a connection to an administration port across zones the topology does not allow
+/// (detection only, never a rejection).
+async fn check_lateral_movement(
+    topology: &TopologyState,
+    store: &dyn TelemetryStore,
+    dashboard: &DashboardCache,
+    agent_id: Uuid,
+    component_id: &str,
+    a: &Endpoint<'_>,
+    b: &Endpoint<'_>,
+) {
+    let Some(movement) = topology.check_lateral(a, b, Instant::now()) else { return };
+    warn!(
+        "Cross-zone lateral movement | from={}/{} | to={}/{} | port={} | agent_id={} | component_id={}",
+        movement.source.site, movement.source.zone, movement.destination.site, movement.destination.zone,
+        movement.port, agent_id, component_id
+    );
+    if let Err(e) = record_lateral_movement(store, dashboard, agent_id, component_id, &movement).await {
+        error!("Failed to record cross-zone lateral movement detection: {}", e);
+    }
 }
 
-// PROMPT-40A: Insert into immutable_audit_log (fail-closed)
-async fn insert_immutable_audit_log(
-    db: &Client,
-    actor_component_id: Option<Uuid>,
-    actor_agent_id: Option<Uuid>,
-    action: &str,
-    object_type: &str,
-    object_id: Option<Uuid>,
-    event_time: Option<chrono::DateTime<chrono::Utc>>,
-    payload_json: &JsonValue,
-    payload_sha256: &[u8],
-) -> Result<Uuid, Box<dyn std::error::Error>> {
-    // Get previous audit chain entry for hash chaining
-    let prev_row = db.query_opt(
-        r#"
-        SELECT audit_id, chain_hash_sha256, payload_sha256
-        FROM immutable_audit_log
-        ORDER BY created_at DESC
-        LIMIT 1
-        "#,
-        &[],
-    ).await?;
-
-    let (prev_audit_id, prev_chain_hash, prev_payload_sha256): (Option<Uuid>, Option<Vec<u8>>, Option<Vec<u8>>) = 
-        if let Some(row) = prev_row {
-            (
-                Some(row.get(0)),
-                Some(row.get::<usize, Vec<u8>>(1)),
-                Some(row.get::<usize, Vec<u8>>(2)),
+async fn record_lateral_movement(
+    store: &dyn TelemetryStore,
+    dashboard: &DashboardCache,
+    agent_id: Uuid,
+    component_id: &str,
+    movement: &CrossZoneMovement,
+) -> Result<Uuid, String> {
+    let addr = |ip: Option<IpAddr>| ip.map(|ip| ip.to_string());
+    let (source_addr, destination_addr) = (addr(movement.source_addr), addr(movement.destination_addr));
+    let detection = DetectionRecord {
+        detection_engine: "ingest_topology".to_string(),
+        detection_name: "cross_zone_lateral_movement".to_string(),
+        detection_category: Some("lateral_movement".to_string()),
+        severity: "warning".to_string(),
+        confidence: 0.7,
+        reasoning: format!(
+            "{} in zone {} (site {}) connected to {}:{} in zone {} (site {}); the topology does not allow administration traffic from {} to {}",
+            source_addr.as_deref().unwrap_or("a host"), movement.source.zone, movement.source.site,
+            destination_addr.as_deref().unwrap_or("a host"), movement.port, movement.destination.zone, movement.destination.site,
+            movement.source.zone, movement.destination.zone
+        ),
+        artifacts: serde_json::json!({
+            "source_site": movement.source.site,
+            "source_zone": movement.source.zone,
+            "destination_site": movement.destination.site,
+            "destination_zone": movement.destination.zone,
+            "source_addr": source_addr,
+            "destination_addr": destination_addr,
+            "port": movement.port,
+            "agent_id": agent_id.to_string(),
+            "component_id": component_id,
+        }),
+        deterministic_key: Sha256::digest(
+            format!(
+                "cross_zone|{}|{}|{}|{}|{}",
+                movement.source.zone,
+                movement.destination.zone,
+                source_addr.as_deref().unwrap_or_default(),
+                destination_addr.as_deref().unwrap_or_default(),
+                movement.port
             )
-        } else {
-            (None, None, None)
-        };
-
-    // Compute chain hash: SHA256(prev_chain_hash || payload_sha256)
-    let prev_chain_hash_bytes = prev_chain_hash.as_deref().unwrap_or(&[0u8; 32]);
-    let mut chain_input = Vec::with_capacity(64);
-    chain_input.extend_from_slice(prev_chain_hash_bytes);
-    chain_input.extend_from_slice(payload_sha256);
-    let mut chain_hasher = Sha256::new();
-    chain_hasher.update(&chain_input);
-    let chain_hash_sha256 = chain_hasher.finalize().to_vec();
-
-    // Insert audit log entry
-    let row = db.query_one(
-        r#"
-        INSERT INTO immutable_audit_log (
-            actor_component_id, actor_agent_id, action, object_type, object_id, event_time,
-            payload_json, payload_sha256, prev_audit_id, prev_payload_sha256, chain_hash_sha256, signature_status
+            .as_bytes(),
         )
-        VALUES ($1, $2, $3, $4::text::trust_object_type, $5, $6, $7, $8, $9, $10, $11, 'unknown')
-        RETURNING audit_id
-        "#,
-        &[
-            &actor_component_id,
-            &actor_agent_id,
-            &action,
-            &object_type,
-            &object_id,
-            &event_time,
-            &payload_json,
-            &payload_sha256,
-            &prev_audit_id,
-            &prev_payload_sha256,
-            &chain_hash_sha256,
-        ],
-    ).await?;
-
-    Ok(row.get(0))
+        .to_vec(),
+    };
+    record_ingest_detection(store, dashboard, &detection, Some(agent_id), "CROSS_ZONE_LATERAL_MOVEMENT").await
 }
 
+/// Check the event's origin against its identity. Returns the response for a quarantined event,
+/// or `None` when ingest continues. A conflict that cannot be recorded fails the request (500)
+/// and is forgotten, so the retried event re-detects it.
+#[allow(clippy::too_many_arguments)]
+async fn screen_identity(
+    conflicts: &IdentityConflicts,
+    store: &dyn TelemetryStore,
+    dashboard: &DashboardCache,
+    component_type: &str,
+    component_id: &str,
+    agent_id: Uuid,
+    origin: IdentityOrigin,
+    message_id: &str,
+    body: &[u8],
+) -> Result<Option<Json<IngestResponse>>, StatusCode> {
+    let conflict_id = match conflicts.observe(component_type, component_id, agent_id, &origin) {
+        IdentityDecision::Accepted => return Ok(None),
+        IdentityDecision::Opened(conflict) => {
+            warn!(
+                "Duplicate agent identity | component_type={} | component_id={} | first_origin={} | conflicting_origin={} | mode={}",
+                component_type, component_id, conflict.first_origin, conflict.conflicting_origin, conflicts.mode.as_str()
+            );
+            if let Err(e) = record_identity_conflict(store, dashboard, &conflict).await {
+                error!("FAIL-CLOSED: Failed to record identity conflict: {}", e);
+                conflicts.resolve(conflict.conflict_id);
+                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+            if conflicts.mode != ConflictMode::Quarantine {
+                return Ok(None);
+            }
+            conflict.conflict_id
+        }
+        IdentityDecision::Quarantined(conflict_id) => conflict_id,
+    };
+
+    let signed_event: Box<RawValue> = serde_json::from_slice(body).map_err(|e| {
+        error!("VALIDATION ERROR: Invalid signed event: {}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+    let event = QuarantinedEventRecord {
+        conflict_id,
+        component_type: component_type.to_string(),
+        component_identity: component_id.to_string(),
+        message_id: message_id.to_string(),
+        origin,
+        signed_event,
+        signed_event_sha256: Sha256::digest(body).to_vec(),
+    };
+    let quarantine_id = quarantine_event(store, agent_id, &event).await.map_err(|e| {
+        error!("FAIL-CLOSED: Failed to quarantine event: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    info!(
+        "Event quarantined | quarantine_id={} | conflict_id={} | component_id={} | message_id={}",
+        quarantine_id, conflict_id, component_id, message_id
+    );
+    Ok(Some(Json(IngestResponse {
+        status: "quarantined".to_string(),
+        message_id: message_id.to_string(),
+    })))
+}
+
+/// Detection + identity_conflicts row + audit entry in one unit of work.
+async fn record_identity_conflict(
+    store: &dyn TelemetryStore,
+    dashboard: &DashboardCache,
+    conflict: &IdentityConflictRecord,
+) -> Result<Uuid, String> {
+    let artifacts = serde_json::json!({
+        "conflict_id": conflict.conflict_id.to_string(),
+        "component_type": conflict.component_type,
+        "component_id": conflict.component_identity,
+        "agent_id": conflict.agent_id.to_string(),
+        "first_origin": conflict.first_origin,
+        "conflicting_origin": conflict.conflicting_origin,
+    });
+    let detection = DetectionRecord {
+        detection_engine: "ingest_identity_conflict".to_string(),
+        detection_name: "duplicate_agent_identity".to_string(),
+        detection_category: Some("identity_spoofing".to_string()),
+        severity: "error".to_string(),
+        confidence: 1.0,
+        reasoning: format!(
+            "{} {} was used concurrently from {} and {}; two hosts appear to share one identity",
+            conflict.component_type, conflict.component_identity, conflict.first_origin, conflict.conflicting_origin
+        ),
+        artifacts: artifacts.clone(),
+        deterministic_key: Sha256::digest(conflict.conflict_id.as_bytes()).to_vec(),
+    };
+    let ingestion_component_id = store.ingestion_component().await.map_err(|e| e.to_string())?;
+    let payload_str = serde_json::to_string(&artifacts).map_err(|e| e.to_string())?;
+    let mut tx = store.begin().await.map_err(|e| e.to_string())?;
+    let detection_id = match tx.insert_detection(&detection).await {
+        Ok(id) => id,
+        Err(e) => {
+            tx.rollback().await;
+            return Err(e.to_string());
+        }
+    };
+    if let Err(e) = tx.insert_identity_conflict(conflict, detection_id).await {
+        tx.rollback().await;
+        return Err(e.to_string());
+    }
+    let audit = AuditRecord {
+        actor_component_id: Some(ingestion_component_id),
+        actor_agent_id: Some(conflict.agent_id),
+        action: "IDENTITY_CONFLICT_DETECTED".to_string(),
+        object_type: "other".to_string(),
+        object_id: Some(conflict.conflict_id),
+        event_time: Some(Utc::now()),
+        payload_json: artifacts,
+        payload_sha256: Sha256::digest(payload_str.as_bytes()).to_vec(),
+    };
+    if let Err(e) = tx.append_audit(&audit).await {
+        tx.rollback().await;
+        return Err(e.to_string());
+    }
+    tx.commit().await.map_err(|e| e.to_string())?;
+    dashboard.invalidate_detections();
+    Ok(detection_id)
+}
+
+/// quarantined_events row + INGEST_QUARANTINE audit entry in one unit of work.
+async fn quarantine_event(store: &dyn TelemetryStore, agent_id: Uuid, event: &QuarantinedEventRecord) -> Result<Uuid, String> {
+    let ingestion_component_id = store.ingestion_component().await.map_err(|e| e.to_string())?;
+    let mut tx = store.begin().await.map_err(|e| e.to_string())?;
+    let quarantine_id = match tx.insert_quarantined_event(event).await {
+        Ok(id) => id,
+        Err(e) => {
+            tx.rollback().await;
+            return Err(e.to_string());
+        }
+    };
+    let payload = serde_json::json!({
+        "quarantine_id": quarantine_id.to_string(),
+        "conflict_id": event.conflict_id.to_string(),
+        "component_type": event.component_type,
+        "component_id": event.component_identity,
+        "message_id": event.message_id,
+        "origin": event.origin,
+        "signed_event_sha256": hex::encode(&event.signed_event_sha256),
+    });
+    let payload_str = serde_json::to_string(&payload).map_err(|e| e.to_string())?;
+    let audit = AuditRecord {
+        actor_component_id: Some(ingestion_component_id),
+        actor_agent_id: Some(agent_id),
+        action: "INGEST_QUARANTINE".to_string(),
+        object_type: "other".to_string(),
+        object_id: Some(quarantine_id),
+        event_time: Some(Utc::now()),
+        payload_json: payload,
+        payload_sha256: Sha256::digest(payload_str.as_bytes()).to_vec(),
+    };
+    if let Err(e) = tx.append_audit(&audit).await {
+        tx.rollback().await;
+        return Err(e.to_string());
+    }
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(quarantine_id)
+}
+
+/// Persist a detection raised by ingest together with its audit entry in one unit of work; the
+/// dashboard's severity counts are dropped once it commits.
+async fn record_ingest_detection(
+    store: &dyn TelemetryStore,
+    dashboard: &DashboardCache,
+    detection: &DetectionRecord,
+    agent_id: Option<Uuid>,
+    audit_action: &str,
+) -> Result<Uuid, String> {
+    let ingestion_component_id = store.ingestion_component().await.map_err(|e| e.to_string())?;
+    let payload_str = serde_json::to_string(&detection.artifacts).map_err(|e| e.to_string())?;
+    let mut tx = store.begin().await.map_err(|e| e.to_string())?;
+    let detection_id = match tx.insert_detection(detection).await {
+        Ok(id) => id,
+        Err(e) => {
+            tx.rollback().await;
+            return Err(e.to_string());
+        }
+    };
+    let audit = AuditRecord {
+        actor_component_id: Some(ingestion_component_id),
+        actor_agent_id: agent_id,
+        action: audit_action.to_string(),
+        object_type: "other".to_string(),
+        object_id: Some(detection_id),
+        event_time: Some(Utc::now()),
+        payload_json: detection.artifacts.clone(),
+        payload_sha256: Sha256::digest(payload_str.as_bytes()).to_vec(),
+    };
+    if let Err(e) = tx.append_audit(&audit).await {
+        tx.rollback().await;
+        return Err(e.to_string());
+    }
+    tx.commit().await.map_err(|e| e.to_string())?;
+    dashboard.invalidate_detections();
+    Ok(detection_id)
+}
+
+/// Roll back an ingest unit of work after a failed step (FAIL-CLOSED: nothing partial is committed).
+async fn abort_tx(tx: Box<dyn StorageTx>, context: &str, e: impl std::fmt::Display) -> StatusCode {
+    error!("FAIL-CLOSED: {}: {}", context, e);
+    tx.rollback().await;
+    StatusCode::INTERNAL_SERVER_ERROR
+}
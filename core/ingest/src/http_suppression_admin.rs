@@ -0,0 +1,326 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/http_suppression_admin.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Authenticated admin endpoints for detection suppression rules - create signed rules, list them with their hit counts and expire them early (all audited)
+
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chrono::{DateTime, SecondsFormat, Utc};
+use crypto::digest::Sha256;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::error::SqlState;
+use tokio_postgres::Client;
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::http_agent_auth;
+use crate::http_list::{ListQuery, ListQueryRejection, ListSpec, Page};
+use crate::http_server::AppState;
+use crate::suppression::{self, Suppression, SuppressionRule};
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateSuppressionRequest {
+    /// Rule document (JSON text) exactly as signed
+    pub rule: String,
+    /// Base64 Ed25519 signature over the UTF-8 bytes of `rule`
+    pub signature: String,
+    /// Hex Ed25519 public key of the signer (must be in RANSOMEYE_INGEST_SUPPRESSION_SIGNERS)
+    pub signer_key: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreateSuppressionResponse {
+    pub suppression_id: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ExpireSuppressionRequest {
+    pub suppression_id: Uuid,
+    pub expired_by: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ExpireSuppressionResponse {
+    /// Rows changed (always 1; an unknown or inactive rule is a 404)
+    pub updated: u64,
+}
+
+const SUPPRESSION_LIST: ListSpec = ListSpec {
+    filterable: &["name", "created_by", "created_at", "expires_at", "hit_count", "last_hit_at", "active"],
+    selectable: &[
+        "suppression_id",
+        "name",
+        "match",
+        "reason",
+        "created_by",
+        "signer_key",
+        "created_at",
+        "expires_at",
+        "expired_at",
+        "expired_by",
+        "expire_reason",
+        "hit_count",
+        "last_hit_at",
+        "active",
+    ],
+};
+
+fn control_db(state: &AppState) -> Result<&Arc<Client>, StatusCode> {
+    state.db.as_ref().ok_or_else(|| {
+        error!("Suppression operation requested but storage backend has no control plane (postgres required)");
+        StatusCode::SERVICE_UNAVAILABLE
+    })
+}
+
+fn db_err(what: &str) -> impl FnOnce(tokio_postgres::Error) -> StatusCode + '_ {
+    move |e| {
+        error!("FAIL-CLOSED: {}: {}", what, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+fn micros_key(at: DateTime<Utc>, id: Uuid) -> String {
+    format!("{}|{}", at.to_rfc3339_opts(SecondsFormat::Micros, true), id)
+}
+
+/// POST /admin/suppressions (X-Admin-Key): activate a signed suppression rule.
+#[utoipa::path(
+    post,
+    path = "/admin/suppressions",
+    tag = "admin",
+    request_body = CreateSuppressionRequest,
+    responses(
+        (status = 200, description = "Rule active", body = CreateSuppressionResponse),
+        (status = 400, description = "Malformed signature or key, or invalid rule (criteria, reason, expiry)"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 403, description = "Untrusted signer or signature does not verify"),
+        (status = 409, description = "This rule document was already submitted"),
+        (status = 503, description = "Admin key, suppression signers or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_create_suppression(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<CreateSuppressionRequest>,
+) -> Result<Json<CreateSuppressionResponse>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    if state.suppression_signers.is_empty() {
+        error!("Suppression rule refused: RANSOMEYE_INGEST_SUPPRESSION_SIGNERS is not configured");
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+    let db = control_db(&state)?;
+    let signer_key = suppression::parse_public_key(req.signer_key.trim()).map_err(|e| {
+        warn!("Rejected suppression rule: {}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+    let signature = STANDARD.decode(req.signature.trim()).map_err(|e| {
+        warn!("Rejected suppression rule: invalid signature base64: {}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+    state
+        .suppression_signers
+        .verify(&signer_key, req.rule.as_bytes(), &signature)
+        .map_err(|e| {
+            warn!("Rejected suppression rule: {}", e);
+            StatusCode::FORBIDDEN
+        })?;
+    let rule = SuppressionRule::parse(&req.rule, Utc::now()).map_err(|e| {
+        warn!("Rejected suppression rule: {}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+
+    let suppression_id = Uuid::new_v4();
+    let rule_sha256 = Sha256::digest(req.rule.as_bytes()).to_vec();
+    let inserted = db
+        .execute(
+            r#"
+            INSERT INTO detection_suppressions (
+                suppression_id, name, rule_json, rule_sha256, signature, signer_key, reason, created_by, expires_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#,
+            &[
+                &suppression_id,
+                &rule.name,
+                &req.rule,
+                &rule_sha256,
+                &signature,
+                &signer_key.as_slice(),
+                &rule.reason,
+                &rule.created_by,
+                &rule.expires_at,
+            ],
+        )
+        .await;
+    if let Err(e) = inserted {
+        if e.code() == Some(&SqlState::UNIQUE_VIOLATION) {
+            warn!("Rejected suppression rule '{}': document already submitted", rule.name);
+            return Err(StatusCode::CONFLICT);
+        }
+        return Err(db_err("Failed to insert suppression rule")(e));
+    }
+
+    http_agent_auth::audit(
+        state.store.as_ref(),
+        None,
+        "SUPPRESSION_CREATED",
+        Some(suppression_id),
+        &serde_json::json!({
+            "suppression_id": suppression_id.to_string(),
+            "name": rule.name,
+            "match": rule.criteria,
+            "reason": rule.reason,
+            "created_by": rule.created_by,
+            "expires_at": rule.expires_at.to_rfc3339(),
+            "signer_key": hex::encode(signer_key),
+            "rule_sha256": hex::encode(&rule_sha256),
+        }),
+    )
+    .await?;
+    info!(
+        "Suppression rule active | suppression_id={} | name={} | by={} | expires_at={}",
+        suppression_id,
+        rule.name,
+        rule.created_by,
+        rule.expires_at.to_rfc3339()
+    );
+    Ok(Json(CreateSuppressionResponse { suppression_id: suppression_id.to_string(), expires_at: rule.expires_at }))
+}
+
+/// GET /admin/suppressions (X-Admin-Key): rules with their hit counts, oldest first, as a list
+/// page. filter[active]=true lists the rules suppressing now.
+#[utoipa::path(
+    get,
+    path = "/admin/suppressions",
+    tag = "admin",
+    params(
+        ("limit" = Option<usize>, Query, description = "Page size (1-500, default 50)"),
+        ("cursor" = Option<String>, Query, description = "next_cursor of the previous page"),
+        ("fields" = Option<String>, Query, description = "Comma-separated sparse fieldset"),
+    ),
+    responses(
+        (status = 200, description = "Page of rules (Suppression items)", body = Page),
+        (status = 400, description = "Unsupported list parameter"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_list_suppressions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<ListQuery, ListQueryRejection>,
+) -> Result<Json<Page>, Response> {
+    state.admin_key.check(&headers).map_err(IntoResponse::into_response)?;
+    let query = query.map_err(IntoResponse::into_response)?;
+    let db = control_db(&state).map_err(IntoResponse::into_response)?;
+    let rows = db
+        .query(
+            r#"
+            SELECT suppression_id, name, rule_json, reason, created_by, signer_key, created_at, expires_at,
+                   expired_at, expired_by, expire_reason, hit_count, last_hit_at
+            FROM detection_suppressions
+            "#,
+            &[],
+        )
+        .await
+        .map_err(db_err("Failed to list suppression rules"))
+        .map_err(IntoResponse::into_response)?;
+    let now = Utc::now();
+    let mut rules = Vec::with_capacity(rows.len());
+    for r in &rows {
+        let suppression_id: Uuid = r.get(0);
+        let document: String = r.get(2);
+        let rule: SuppressionRule = serde_json::from_str(&document).map_err(|e| {
+            error!("FAIL-CLOSED: detection_suppressions {} has an unreadable rule: {}", suppression_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        })?;
+        let signer_key: Vec<u8> = r.get(5);
+        let expires_at: DateTime<Utc> = r.get(7);
+        let expired_at: Option<DateTime<Utc>> = r.get(8);
+        rules.push(Suppression {
+            suppression_id,
+            name: r.get(1),
+            criteria: rule.criteria,
+            reason: r.get(3),
+            created_by: r.get(4),
+            signer_key: hex::encode(signer_key),
+            created_at: r.get(6),
+            expires_at,
+            expired_at,
+            expired_by: r.get(9),
+            expire_reason: r.get(10),
+            hit_count: r.get(11),
+            last_hit_at: r.get(12),
+            active: expired_at.is_none() && expires_at > now,
+        });
+    }
+    query
+        .paginate(&SUPPRESSION_LIST, rules, |s| micros_key(s.created_at, s.suppression_id))
+        .map(Json)
+        .map_err(IntoResponse::into_response)
+}
+
+/// POST /admin/suppressions/expire (X-Admin-Key): stop an active rule before its expiry; the
+/// row stays as history with its hit count.
+#[utoipa::path(
+    post,
+    path = "/admin/suppressions/expire",
+    tag = "admin",
+    request_body = ExpireSuppressionRequest,
+    responses(
+        (status = 200, description = "Rule expired", body = ExpireSuppressionResponse),
+        (status = 400, description = "Empty reason or expired_by"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 404, description = "No active rule with this id"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_expire_suppression(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<ExpireSuppressionRequest>,
+) -> Result<Json<ExpireSuppressionResponse>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    if req.reason.trim().is_empty() || req.expired_by.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let updated = control_db(&state)?
+        .execute(
+            r#"
+            UPDATE detection_suppressions
+            SET expired_at = now(), expired_by = $2, expire_reason = $3
+            WHERE suppression_id = $1 AND expired_at IS NULL AND expires_at > now()
+            "#,
+            &[&req.suppression_id, &req.expired_by, &req.reason],
+        )
+        .await
+        .map_err(db_err("Failed to expire suppression rule"))?;
+    if updated == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    http_agent_auth::audit(
+        state.store.as_ref(),
+        None,
+        "SUPPRESSION_EXPIRED",
+        Some(req.suppression_id),
+        &serde_json::json!({
+            "suppression_id": req.suppression_id.to_string(),
+            "expired_by": req.expired_by,
+            "reason": req.reason,
+        }),
+    )
+    .await?;
+    info!("Suppression rule expired | suppression_id={} | by={}", req.suppression_id, req.expired_by);
+    Ok(Json(ExpireSuppressionResponse { updated }))
+}
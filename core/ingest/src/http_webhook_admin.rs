@@ -0,0 +1,386 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/http_webhook_admin.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Authenticated admin endpoints for lifecycle webhooks - register/list/disable subscriptions, query delivery history and re-drive dead-lettered deliveries (all audited)
+
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::http_agent_auth;
+use crate::http_list::{ListQuery, ListQueryRejection, ListSpec, Page};
+use crate::http_server::AppState;
+use crate::webhooks::{self, DeliveryStatus, WebhookDelivery, WebhookSubscription};
+
+/// Signing secret size (bytes) issued per subscription.
+const SECRET_BYTES: usize = 32;
+
+/// Delivery history served per query, newest first (older rows stay in the table for SQL access).
+const DELIVERY_HISTORY_WINDOW: i64 = 10_000;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RegisterWebhookRequest {
+    pub url: String,
+    /// Event types to receive (agent.enrolled, detection.created, retention.run, retention.alert, disk.quota, db.capacity, operator.break_glass) or ["*"].
+    pub event_types: Vec<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RegisterWebhookResponse {
+    pub webhook_id: String,
+    /// Hex HMAC-SHA256 signing secret. Shown only in this response.
+    pub secret: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DisableWebhookRequest {
+    pub webhook_id: Uuid,
+    pub reason: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RedriveDeliveryRequest {
+    pub delivery_id: Uuid,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WebhookChangeResponse {
+    /// Rows changed (0 or 1)
+    pub updated: u64,
+}
+
+const SUBSCRIPTION_LIST: ListSpec = ListSpec {
+    filterable: &["url", "enabled", "created_at"],
+    selectable: &["webhook_id", "url", "event_types", "description", "enabled", "created_at"],
+};
+
+const DELIVERY_LIST: ListSpec = ListSpec {
+    filterable: &["webhook_id", "event_id", "event_type", "status", "attempts", "created_at"],
+    selectable: &[
+        "delivery_id",
+        "webhook_id",
+        "event_id",
+        "event_type",
+        "status",
+        "attempts",
+        "last_status_code",
+        "last_error",
+        "created_at",
+        "next_attempt_at",
+        "delivered_at",
+    ],
+};
+
+fn control_db(state: &AppState) -> Result<&Arc<Client>, StatusCode> {
+    state.db.as_ref().ok_or_else(|| {
+        error!("Webhook operation requested but storage backend has no control plane (postgres required)");
+        StatusCode::SERVICE_UNAVAILABLE
+    })
+}
+
+fn db_err(what: &str) -> impl FnOnce(tokio_postgres::Error) -> StatusCode + '_ {
+    move |e| {
+        error!("FAIL-CLOSED: {}: {}", what, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+fn micros_key(at: DateTime<Utc>, id: Uuid) -> String {
+    format!("{}|{}", at.to_rfc3339_opts(SecondsFormat::Micros, true), id)
+}
+
+/// POST /admin/webhooks (X-Admin-Key): register a receiver; the signing secret is returned once.
+#[utoipa::path(
+    post,
+    path = "/admin/webhooks",
+    tag = "admin",
+    request_body = RegisterWebhookRequest,
+    responses(
+        (status = 200, description = "Subscription registered", body = RegisterWebhookResponse),
+        (status = 400, description = "Invalid url or event type"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_register(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<RegisterWebhookRequest>,
+) -> Result<Json<RegisterWebhookResponse>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    let db = control_db(&state)?;
+    if let Err(reason) = webhooks::validate_subscription(&req.url, &req.event_types) {
+        warn!("Rejected webhook registration: {}", reason);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mut secret = [0u8; SECRET_BYTES];
+    crypto::rand::fill(&mut secret).map_err(|_| {
+        error!("FAIL-CLOSED: Failed to generate webhook secret");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let webhook_id = Uuid::new_v4();
+    db.execute(
+        r#"
+        INSERT INTO webhook_subscriptions (webhook_id, url, secret, event_types, description)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        &[&webhook_id, &req.url, &secret.as_slice(), &req.event_types, &req.description],
+    )
+    .await
+    .map_err(db_err("Failed to insert webhook subscription"))?;
+
+    // Never audit the secret
+    http_agent_auth::audit(
+        state.store.as_ref(),
+        None,
+        "WEBHOOK_REGISTERED",
+        Some(webhook_id),
+        &serde_json::json!({
+            "webhook_id": webhook_id.to_string(),
+            "url": req.url,
+            "event_types": req.event_types,
+        }),
+    )
+    .await?;
+    info!("Webhook registered | webhook_id={} | url={} | event_types={:?}", webhook_id, req.url, req.event_types);
+    Ok(Json(RegisterWebhookResponse {
+        webhook_id: webhook_id.to_string(),
+        secret: hex::encode(secret),
+    }))
+}
+
+/// GET /admin/webhooks (X-Admin-Key): subscriptions, oldest first, as a list page (no secrets).
+#[utoipa::path(
+    get,
+    path = "/admin/webhooks",
+    tag = "admin",
+    params(
+        ("limit" = Option<usize>, Query, description = "Page size (1-500, default 50)"),
+        ("cursor" = Option<String>, Query, description = "next_cursor of the previous page"),
+        ("fields" = Option<String>, Query, description = "Comma-separated sparse fieldset"),
+    ),
+    responses(
+        (status = 200, description = "Page of subscriptions (WebhookSubscription items)", body = Page),
+        (status = 400, description = "Unsupported list parameter"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_list_webhooks(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<ListQuery, ListQueryRejection>,
+) -> Result<Json<Page>, Response> {
+    state.admin_key.check(&headers).map_err(IntoResponse::into_response)?;
+    let query = query.map_err(IntoResponse::into_response)?;
+    let db = control_db(&state).map_err(IntoResponse::into_response)?;
+    let rows = db
+        .query(
+            "SELECT webhook_id, url, event_types, description, enabled, created_at FROM webhook_subscriptions",
+            &[],
+        )
+        .await
+        .map_err(db_err("Failed to list webhook subscriptions"))
+        .map_err(IntoResponse::into_response)?;
+    let subscriptions: Vec<WebhookSubscription> = rows
+        .iter()
+        .map(|r| WebhookSubscription {
+            webhook_id: r.get(0),
+            url: r.get(1),
+            event_types: r.get(2),
+            description: r.get(3),
+            enabled: r.get(4),
+            created_at: r.get(5),
+        })
+        .collect();
+    query
+        .paginate(&SUBSCRIPTION_LIST, subscriptions, |s| micros_key(s.created_at, s.webhook_id))
+        .map(Json)
+        .map_err(IntoResponse::into_response)
+}
+
+/// POST /admin/webhooks/disable (X-Admin-Key): stop new deliveries; queued ones are still attempted.
+#[utoipa::path(
+    post,
+    path = "/admin/webhooks/disable",
+    tag = "admin",
+    request_body = DisableWebhookRequest,
+    responses(
+        (status = 200, description = "Subscription disabled (updated = 0 if already disabled)", body = WebhookChangeResponse),
+        (status = 400, description = "Empty reason"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_disable_webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<DisableWebhookRequest>,
+) -> Result<Json<WebhookChangeResponse>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    if req.reason.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let updated = control_db(&state)?
+        .execute(
+            "UPDATE webhook_subscriptions SET enabled = false, disabled_at = now() WHERE webhook_id = $1 AND enabled",
+            &[&req.webhook_id],
+        )
+        .await
+        .map_err(db_err("Failed to disable webhook subscription"))?;
+    http_agent_auth::audit(
+        state.store.as_ref(),
+        None,
+        "WEBHOOK_DISABLED",
+        Some(req.webhook_id),
+        &serde_json::json!({
+            "webhook_id": req.webhook_id.to_string(),
+            "reason": req.reason,
+            "updated": updated,
+        }),
+    )
+    .await?;
+    info!("Webhook disabled | webhook_id={} | updated={}", req.webhook_id, updated);
+    Ok(Json(WebhookChangeResponse { updated }))
+}
+
+/// GET /admin/webhooks/deliveries (X-Admin-Key): delivery history, newest first, as a list page.
+/// filter[status]=dead lists the dead-letter set.
+#[utoipa::path(
+    get,
+    path = "/admin/webhooks/deliveries",
+    tag = "admin",
+    params(
+        ("limit" = Option<usize>, Query, description = "Page size (1-500, default 50)"),
+        ("cursor" = Option<String>, Query, description = "next_cursor of the previous page"),
+        ("fields" = Option<String>, Query, description = "Comma-separated sparse fieldset"),
+    ),
+    responses(
+        (status = 200, description = "Page of deliveries (WebhookDelivery items)", body = Page),
+        (status = 400, description = "Unsupported list parameter"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_list_deliveries(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<ListQuery, ListQueryRejection>,
+) -> Result<Json<Page>, Response> {
+    state.admin_key.check(&headers).map_err(IntoResponse::into_response)?;
+    let query = query.map_err(IntoResponse::into_response)?;
+    let db = control_db(&state).map_err(IntoResponse::into_response)?;
+    let rows = db
+        .query(
+            r#"
+            SELECT delivery_id, webhook_id, event_id, event_type, status, attempts, last_status_code,
+                   last_error, created_at, next_attempt_at, delivered_at
+            FROM webhook_deliveries
+            ORDER BY created_at DESC
+            LIMIT $1
+            "#,
+            &[&DELIVERY_HISTORY_WINDOW],
+        )
+        .await
+        .map_err(db_err("Failed to list webhook deliveries"))
+        .map_err(IntoResponse::into_response)?;
+    let mut deliveries = Vec::with_capacity(rows.len());
+    for r in &rows {
+        let status: String = r.get(4);
+        let status = DeliveryStatus::parse(&status).ok_or_else(|| {
+            error!("FAIL-CLOSED: webhook_deliveries has unknown status '{}'", status);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        })?;
+        deliveries.push(WebhookDelivery {
+            delivery_id: r.get(0),
+            webhook_id: r.get(1),
+            event_id: r.get(2),
+            event_type: r.get(3),
+            status,
+            attempts: r.get(5),
+            last_status_code: r.get(6),
+            last_error: r.get(7),
+            created_at: r.get(8),
+            next_attempt_at: r.get(9),
+            delivered_at: r.get(10),
+        });
+    }
+    // Newest first: invert the timestamp so ascending key order is descending time
+    query
+        .paginate(&DELIVERY_LIST, deliveries, |d| {
+            format!("{:020}|{}", i64::MAX - d.created_at.timestamp_micros(), d.delivery_id)
+        })
+        .map(Json)
+        .map_err(IntoResponse::into_response)
+}
+
+/// POST /admin/webhooks/deliveries/redrive (X-Admin-Key): return a dead delivery to the queue
+/// with a fresh attempt budget.
+#[utoipa::path(
+    post,
+    path = "/admin/webhooks/deliveries/redrive",
+    tag = "admin",
+    request_body = RedriveDeliveryRequest,
+    responses(
+        (status = 200, description = "Delivery queued again", body = WebhookChangeResponse),
+        (status = 400, description = "Empty reason"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 404, description = "No dead delivery with this id"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_redrive_delivery(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<RedriveDeliveryRequest>,
+) -> Result<Json<WebhookChangeResponse>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    if req.reason.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let updated = control_db(&state)?
+        .execute(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = 'pending', attempts = 0, next_attempt_at = now(), last_error = NULL, last_status_code = NULL
+            WHERE delivery_id = $1 AND status = 'dead'
+            "#,
+            &[&req.delivery_id],
+        )
+        .await
+        .map_err(db_err("Failed to re-drive webhook delivery"))?;
+    if updated == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    http_agent_auth::audit(
+        state.store.as_ref(),
+        None,
+        "WEBHOOK_DELIVERY_REDRIVEN",
+        Some(req.delivery_id),
+        &serde_json::json!({
+            "delivery_id": req.delivery_id.to_string(),
+            "reason": req.reason,
+        }),
+    )
+    .await?;
+    info!("Webhook delivery re-driven | delivery_id={}", req.delivery_id);
+    Ok(Json(WebhookChangeResponse { updated }))
+}
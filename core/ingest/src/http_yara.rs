@@ -0,0 +1,504 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/http_yara.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: YARA endpoints - operators publish signed rule packs and request on-demand scans; Linux agents poll for the current pack and their pending scan (agent bearer token, audited)
+
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+    Extension,
+};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chrono::{DateTime, SecondsFormat, Utc};
+use crypto::digest::Sha256;
+use serde::Serialize;
+use tokio_postgres::error::SqlState;
+use tokio_postgres::Client;
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::feature_flags::Flag;
+use crate::http_agent_auth::{self, AuthenticatedAgent};
+use crate::http_list::{ListQuery, ListQueryRejection, ListSpec, Page};
+use crate::http_server::AppState;
+use crate::suppression;
+use crate::yara_rules::{
+    self, RulePackOrder, ScanOrder, ScanRequest, YaraPollRequest, YaraPollResponse, YaraRulePack, YaraRulePackEntry,
+    YaraScan,
+};
+
+#[derive(Debug, serde::Deserialize, ToSchema)]
+pub struct CreateRulePackRequest {
+    /// Pack document (JSON text) exactly as signed
+    pub document: String,
+    /// Base64 Ed25519 signature over the UTF-8 bytes of `document`
+    pub signature: String,
+    /// Hex Ed25519 public key of the signer (must be in RANSOMEYE_INGEST_YARA_SIGNERS)
+    pub signer_key: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreateRulePackResponse {
+    pub pack_id: String,
+    pub version: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ScanRequestResponse {
+    pub scan_id: String,
+}
+
+const RULE_PACK_LIST: ListSpec = ListSpec {
+    filterable: &["name", "version", "created_by", "created_at", "current"],
+    selectable: &[
+        "pack_id",
+        "name",
+        "version",
+        "document_sha256",
+        "signer_key",
+        "rules_bytes",
+        "reason",
+        "created_by",
+        "created_at",
+        "current",
+    ],
+};
+
+const SCAN_LIST: ListSpec = ListSpec {
+    filterable: &["agent_id", "status", "requested_by", "requested_at"],
+    selectable: &["scan_id", "agent_id", "paths", "reason", "requested_by", "status", "requested_at", "claimed_at"],
+};
+
+fn control_db(state: &AppState) -> Result<&Arc<Client>, StatusCode> {
+    state.db.as_ref().ok_or_else(|| {
+        error!("YARA operation requested but storage backend has no control plane (postgres required)");
+        StatusCode::SERVICE_UNAVAILABLE
+    })
+}
+
+fn db_err(what: &str) -> impl FnOnce(tokio_postgres::Error) -> StatusCode + '_ {
+    move |e| {
+        error!("FAIL-CLOSED: {}: {}", what, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+/// The control channel is for token-authenticated Linux agents only.
+fn linux_agent(auth: Option<Extension<AuthenticatedAgent>>) -> Result<AuthenticatedAgent, StatusCode> {
+    let Some(Extension(auth)) = auth else {
+        warn!("AUTH REJECT: YARA control channel requires an agent token");
+        return Err(StatusCode::UNAUTHORIZED);
+    };
+    if auth.agent_type != "linux_agent" {
+        warn!("AUTH REJECT: agent {} ({}) is not a Linux agent", auth.agent_id, auth.agent_type);
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(auth)
+}
+
+fn micros_key(at: DateTime<Utc>, id: Uuid) -> String {
+    format!("{}|{}", at.to_rfc3339_opts(SecondsFormat::Micros, true), id)
+}
+
+/// POST /admin/yara-rule-packs (X-Admin-Key): publish a signed rule pack to every Linux agent.
+#[utoipa::path(
+    post,
+    path = "/admin/yara-rule-packs",
+    tag = "admin",
+    request_body = CreateRulePackRequest,
+    responses(
+        (status = 200, description = "Pack published", body = CreateRulePackResponse),
+        (status = 400, description = "Malformed signature or key, or invalid pack (name, version, rules, reason)"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 403, description = "Untrusted signer or signature does not verify"),
+        (status = 409, description = "Version not above the current pack, or document already submitted"),
+        (status = 503, description = "Admin key, YARA signers or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_create_rule_pack(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<CreateRulePackRequest>,
+) -> Result<Json<CreateRulePackResponse>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    if state.yara_signers.is_empty() {
+        error!("YARA rule pack refused: RANSOMEYE_INGEST_YARA_SIGNERS is not configured");
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+    let db = control_db(&state)?;
+    let signer_key = suppression::parse_public_key(req.signer_key.trim()).map_err(|e| {
+        warn!("Rejected YARA rule pack: {}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+    let signature = STANDARD.decode(req.signature.trim()).map_err(|e| {
+        warn!("Rejected YARA rule pack: invalid signature base64: {}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+    state.yara_signers.verify(&signer_key, req.document.as_bytes(), &signature).map_err(|e| {
+        warn!("Rejected YARA rule pack: {}", e);
+        StatusCode::FORBIDDEN
+    })?;
+    let pack = YaraRulePack::parse(&req.document).map_err(|e| {
+        warn!("Rejected YARA rule pack: {}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+
+    // Versions only go up, so an older signed pack cannot be rolled back in
+    let pack_id = Uuid::new_v4();
+    let document_sha256 = Sha256::digest(req.document.as_bytes()).to_vec();
+    let inserted = db
+        .execute(
+            r#"
+            INSERT INTO yara_rule_packs (
+                pack_id, name, version, document, document_sha256, signature, signer_key, rules_bytes, reason, created_by
+            )
+            SELECT $1, $2, $3, $4, $5, $6, $7, $8, $9, $10
+            WHERE NOT EXISTS (SELECT 1 FROM yara_rule_packs WHERE version >= $3)
+            "#,
+            &[
+                &pack_id,
+                &pack.name,
+                &pack.version,
+                &req.document,
+                &document_sha256,
+                &signature,
+                &signer_key.as_slice(),
+                &(pack.rules.len() as i32),
+                &pack.reason,
+                &pack.created_by,
+            ],
+        )
+        .await;
+    match inserted {
+        Ok(1) => {}
+        Ok(_) => {
+            warn!("Rejected YARA rule pack '{}': version {} is not above the current pack", pack.name, pack.version);
+            return Err(StatusCode::CONFLICT);
+        }
+        Err(e) if e.code() == Some(&SqlState::UNIQUE_VIOLATION) => {
+            warn!("Rejected YARA rule pack '{}': version or document already submitted", pack.name);
+            return Err(StatusCode::CONFLICT);
+        }
+        Err(e) => return Err(db_err("Failed to insert YARA rule pack")(e)),
+    }
+
+    http_agent_auth::audit(
+        state.store.as_ref(),
+        None,
+        "YARA_RULE_PACK_PUBLISHED",
+        Some(pack_id),
+        &serde_json::json!({
+            "pack_id": pack_id.to_string(),
+            "name": pack.name,
+            "version": pack.version,
+            "reason": pack.reason,
+            "created_by": pack.created_by,
+            "rules_bytes": pack.rules.len(),
+            "signer_key": hex::encode(signer_key),
+            "document_sha256": hex::encode(&document_sha256),
+        }),
+    )
+    .await?;
+    info!(
+        "YARA rule pack published | pack_id={} | name={} | version={} | by={}",
+        pack_id, pack.name, pack.version, pack.created_by
+    );
+    Ok(Json(CreateRulePackResponse { pack_id: pack_id.to_string(), version: pack.version }))
+}
+
+/// GET /admin/yara-rule-packs (X-Admin-Key): published packs without their rules, oldest first,
+/// as a list page; filter[current]=true selects the pack agents are handed.
+#[utoipa::path(
+    get,
+    path = "/admin/yara-rule-packs",
+    tag = "admin",
+    params(
+        ("limit" = Option<usize>, Query, description = "Page size (1-500, default 50)"),
+        ("cursor" = Option<String>, Query, description = "next_cursor of the previous page"),
+        ("fields" = Option<String>, Query, description = "Comma-separated sparse fieldset"),
+    ),
+    responses(
+        (status = 200, description = "Page of rule packs (YaraRulePackEntry items)", body = Page),
+        (status = 400, description = "Unsupported list parameter"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_list_rule_packs(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<ListQuery, ListQueryRejection>,
+) -> Result<Json<Page>, Response> {
+    state.admin_key.check(&headers).map_err(IntoResponse::into_response)?;
+    let query = query.map_err(IntoResponse::into_response)?;
+    let db = control_db(&state).map_err(IntoResponse::into_response)?;
+    let rows = db
+        .query(
+            r#"
+            SELECT pack_id, name, version, document_sha256, signer_key, rules_bytes, reason, created_by, created_at,
+                   version = max(version) OVER ()
+            FROM yara_rule_packs
+            "#,
+            &[],
+        )
+        .await
+        .map_err(db_err("Failed to list YARA rule packs"))
+        .map_err(IntoResponse::into_response)?;
+    let packs: Vec<YaraRulePackEntry> = rows
+        .iter()
+        .map(|r| YaraRulePackEntry {
+            pack_id: r.get(0),
+            name: r.get(1),
+            version: r.get(2),
+            document_sha256: hex::encode(r.get::<_, Vec<u8>>(3)),
+            signer_key: hex::encode(r.get::<_, Vec<u8>>(4)),
+            rules_bytes: r.get(5),
+            reason: r.get(6),
+            created_by: r.get(7),
+            created_at: r.get(8),
+            current: r.get(9),
+        })
+        .collect();
+    query
+        .paginate(&RULE_PACK_LIST, packs, |p| micros_key(p.created_at, p.pack_id))
+        .map(Json)
+        .map_err(IntoResponse::into_response)
+}
+
+/// POST /admin/yara-scans (X-Admin-Key): ask a Linux agent for an immediate YARA scan.
+#[utoipa::path(
+    post,
+    path = "/admin/yara-scans",
+    tag = "admin",
+    request_body = ScanRequest,
+    responses(
+        (status = 200, description = "Scan requested", body = ScanRequestResponse),
+        (status = 400, description = "Invalid paths, reason or requester"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 404, description = "No active Linux agent with this id"),
+        (status = 409, description = "The agent already has a scan pending"),
+        (status = 503, description = "yara_scanning feature flag off for the agent's tenant, admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_request_scan(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<ScanRequest>,
+) -> Result<Json<ScanRequestResponse>, StatusCode> {
+    state.admin_key.check(&headers)?;
+    if let Err(reason) = req.validate() {
+        warn!("Refused YARA scan for agent {}: {}", req.agent_id, reason);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let db = control_db(&state)?;
+    yara_rules::expire_stale(db).await.map_err(db_err("Failed to expire stale YARA scans"))?;
+    let agent = db
+        .query_opt(
+            "SELECT tenant_id FROM agents WHERE agent_id = $1 AND agent_type::text = 'linux_agent' AND is_active",
+            &[&req.agent_id],
+        )
+        .await
+        .map_err(db_err("Failed to look up agent"))?;
+    let Some(agent) = agent else {
+        warn!("Refused YARA scan: {} is not an active Linux agent", req.agent_id);
+        return Err(StatusCode::NOT_FOUND);
+    };
+    let tenant: Option<String> = agent.get(0);
+    if !state.feature_flags.is_enabled(Flag::YaraScanning, tenant.as_deref()) {
+        warn!("Refused YARA scan for agent {}: feature flag {} is off", req.agent_id, Flag::YaraScanning.as_str());
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    let scan_id = Uuid::new_v4();
+    let inserted = db
+        .execute(
+            "INSERT INTO yara_scan_requests (scan_id, agent_id, paths, reason, requested_by) VALUES ($1, $2, $3, $4, $5)",
+            &[&scan_id, &req.agent_id, &req.paths, &req.reason, &req.requested_by],
+        )
+        .await;
+    if let Err(e) = inserted {
+        if e.code() == Some(&SqlState::UNIQUE_VIOLATION) {
+            warn!("Refused YARA scan: agent {} already has one pending", req.agent_id);
+            return Err(StatusCode::CONFLICT);
+        }
+        return Err(db_err("Failed to insert YARA scan request")(e));
+    }
+
+    http_agent_auth::audit(
+        state.store.as_ref(),
+        None,
+        "YARA_SCAN_REQUESTED",
+        Some(scan_id),
+        &serde_json::json!({
+            "scan_id": scan_id.to_string(),
+            "agent_id": req.agent_id.to_string(),
+            "paths": req.paths,
+            "reason": req.reason,
+            "requested_by": req.requested_by,
+        }),
+    )
+    .await?;
+    info!(
+        "YARA scan requested | scan_id={} | agent_id={} | paths={} | by={}",
+        scan_id,
+        req.agent_id,
+        req.paths.len(),
+        req.requested_by
+    );
+    Ok(Json(ScanRequestResponse { scan_id: scan_id.to_string() }))
+}
+
+/// GET /admin/yara-scans (X-Admin-Key): on-demand scan requests, oldest first, as a list page;
+/// filter[agent_id]=<id> selects the scans of one host.
+#[utoipa::path(
+    get,
+    path = "/admin/yara-scans",
+    tag = "admin",
+    params(
+        ("limit" = Option<usize>, Query, description = "Page size (1-500, default 50)"),
+        ("cursor" = Option<String>, Query, description = "next_cursor of the previous page"),
+        ("fields" = Option<String>, Query, description = "Comma-separated sparse fieldset"),
+    ),
+    responses(
+        (status = 200, description = "Page of scan requests (YaraScan items)", body = Page),
+        (status = 400, description = "Unsupported list parameter"),
+        (status = 401, description = "Invalid admin key"),
+        (status = 503, description = "Admin key or postgres control plane not configured"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn handle_list_scans(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<ListQuery, ListQueryRejection>,
+) -> Result<Json<Page>, Response> {
+    state.admin_key.check(&headers).map_err(IntoResponse::into_response)?;
+    let query = query.map_err(IntoResponse::into_response)?;
+    let db = control_db(&state).map_err(IntoResponse::into_response)?;
+    yara_rules::expire_stale(db)
+        .await
+        .map_err(db_err("Failed to expire stale YARA scans"))
+        .map_err(IntoResponse::into_response)?;
+    let rows = db
+        .query(
+            r#"
+            SELECT scan_id, agent_id, paths, reason, requested_by, status, requested_at, claimed_at
+            FROM yara_scan_requests
+            "#,
+            &[],
+        )
+        .await
+        .map_err(db_err("Failed to list YARA scans"))
+        .map_err(IntoResponse::into_response)?;
+    let scans: Vec<YaraScan> = rows
+        .iter()
+        .map(|r| YaraScan {
+            scan_id: r.get(0),
+            agent_id: r.get(1),
+            paths: r.get(2),
+            reason: r.get(3),
+            requested_by: r.get(4),
+            status: r.get(5),
+            requested_at: r.get(6),
+            claimed_at: r.get(7),
+        })
+        .collect();
+    query
+        .paginate(&SCAN_LIST, scans, |s| micros_key(s.requested_at, s.scan_id))
+        .map(Json)
+        .map_err(IntoResponse::into_response)
+}
+
+/// POST /agents/yara/poll (Bearer, Linux agent): the current rule pack when it is newer than
+/// the agent's, and this agent's pending scan, if any (claimed by this call).
+#[utoipa::path(
+    post,
+    path = "/agents/yara/poll",
+    tag = "agents",
+    request_body = YaraPollRequest,
+    responses(
+        (status = 200, description = "Newer pack and/or scan to run now (both absent: nothing to do; no scan while the yara_scanning feature flag is off)", body = YaraPollResponse),
+        (status = 401, description = "Missing or invalid agent token"),
+        (status = 403, description = "Agent is not a Linux agent"),
+        (status = 503, description = "Postgres control plane not configured"),
+    ),
+    security(("agent_token" = []))
+)]
+pub async fn handle_poll(
+    State(state): State<AppState>,
+    auth: Option<Extension<AuthenticatedAgent>>,
+    Json(req): Json<YaraPollRequest>,
+) -> Result<Json<YaraPollResponse>, StatusCode> {
+    let auth = linux_agent(auth)?;
+    let db = control_db(&state)?;
+    yara_rules::expire_stale(db).await.map_err(db_err("Failed to expire stale YARA scans"))?;
+
+    let pack = db
+        .query_opt(
+            r#"
+            SELECT pack_id, version, document, signature, signer_key
+            FROM yara_rule_packs
+            WHERE version > $1
+            ORDER BY version DESC
+            LIMIT 1
+            "#,
+            &[&req.pack_version.unwrap_or(0)],
+        )
+        .await
+        .map_err(db_err("Failed to look up YARA rule pack"))?
+        .map(|r| RulePackOrder {
+            pack_id: r.get(0),
+            version: r.get(1),
+            document: r.get(2),
+            signature: STANDARD.encode(r.get::<_, Vec<u8>>(3)),
+            signer_key: hex::encode(r.get::<_, Vec<u8>>(4)),
+        });
+
+    // Switched off: rule packs still roll out; a pending scan waits until the flag is back on or it expires
+    let scan = if state.feature_flags.is_enabled(Flag::YaraScanning, auth.tenant_id.as_deref()) {
+        db.query_opt(
+            r#"
+            UPDATE yara_scan_requests
+            SET status = 'claimed', claimed_at = now()
+            WHERE agent_id = $1 AND status = 'requested'
+            RETURNING scan_id, paths
+            "#,
+            &[&auth.agent_id],
+        )
+        .await
+        .map_err(db_err("Failed to claim YARA scan"))?
+        .map(|r| ScanOrder { scan_id: r.get(0), paths: r.get(1) })
+    } else {
+        None
+    };
+
+    if let Some(scan) = &scan {
+        http_agent_auth::audit(
+            state.store.as_ref(),
+            Some(auth.agent_id),
+            "YARA_SCAN_CLAIMED",
+            Some(scan.scan_id),
+            &serde_json::json!({
+                "scan_id": scan.scan_id.to_string(),
+                "agent_id": auth.agent_id.to_string(),
+                "component_identity": auth.component_identity,
+                "paths": scan.paths,
+            }),
+        )
+        .await?;
+        info!("YARA scan claimed | scan_id={} | agent={}", scan.scan_id, auth.component_identity);
+    }
+    if let Some(pack) = &pack {
+        info!(
+            "YARA rule pack handed out | version={} | agent={} | agent_version={:?}",
+            pack.version, auth.component_identity, req.pack_version
+        );
+    }
+    Ok(Json(YaraPollResponse { pack, scan }))
+}
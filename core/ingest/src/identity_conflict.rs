@@ -0,0 +1,228 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/identity_conflict.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Duplicate agent-id detection - one component identity used concurrently from different origins (source IP / host id) opens a conflict that quarantines its events until resolved
+
+/*
+ * Duplicate Identity Detection
+ *
+ * A component identity (component type + envelope component_id) is expected to come from one
+ * origin: the peer address and, when the producer reports it, the envelope host_id. The same
+ * identity seen from a second origin while the first is still active (within the conflict
+ * window) means two hosts share one identity, e.g. a cloned image or a copied agent config.
+ *
+ * The conflict is recorded once as a detection and persisted in identity_conflicts. In
+ * quarantine mode every later event of that identity, from either origin, goes to
+ * quarantined_events instead of raw_events/telemetry until an operator resolves the conflict
+ * (POST /admin/identity-conflicts/resolve).
+ *
+ * An origin change after a full window without traffic from the old origin is a move (DHCP
+ * renewal, re-addressing), not a conflict.
+ */
+
+use std::time::{Duration as StdDuration, Instant};
+
+use chrono::{DateTime, Utc};
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::storage::{IdentityConflictRecord, IdentityOrigin};
+
+const DEFAULT_WINDOW_SECS: u64 = 300;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictMode {
+    /// Conflicts are reported and the identity's events are quarantined until resolved (default).
+    Quarantine,
+    /// Conflicts are reported; events keep flowing into telemetry.
+    Detect,
+    /// No duplicate-identity tracking.
+    Disabled,
+}
+
+impl ConflictMode {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "quarantine" => Ok(ConflictMode::Quarantine),
+            "detect" => Ok(ConflictMode::Detect),
+            "disabled" => Ok(ConflictMode::Disabled),
+            other => Err(format!(
+                "Invalid RANSOMEYE_INGEST_IDENTITY_CONFLICTS '{}' (expected quarantine|detect|disabled)",
+                other
+            )),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConflictMode::Quarantine => "quarantine",
+            ConflictMode::Detect => "detect",
+            ConflictMode::Disabled => "disabled",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum IdentityDecision {
+    /// No conflict (or quarantine not enforced); ingest continues.
+    Accepted,
+    /// This event opened a conflict: record it, then handle the event per mode.
+    Opened(IdentityConflictRecord),
+    /// The identity has an open conflict; the event belongs in quarantine.
+    Quarantined(Uuid),
+}
+
+struct SeenOrigin {
+    origin: IdentityOrigin,
+    last_seen: Instant,
+}
+
+type IdentityKey = (String, String);
+
+pub struct IdentityConflicts {
+    pub mode: ConflictMode,
+    window: StdDuration,
+    last_seen: DashMap<IdentityKey, SeenOrigin>,
+    open: DashMap<IdentityKey, IdentityConflictRecord>,
+}
+
+/// Two origins conflict when an attribute known on both sides differs.
+fn origins_conflict(a: &IdentityOrigin, b: &IdentityOrigin) -> bool {
+    let differs = |x: Option<&str>, y: Option<&str>| matches!((x, y), (Some(x), Some(y)) if x != y);
+    matches!((a.source_ip, b.source_ip), (Some(x), Some(y)) if x != y)
+        || differs(a.host_id.as_deref(), b.host_id.as_deref())
+}
+
+impl IdentityConflicts {
+    pub fn from_env() -> Result<Self, String> {
+        let mode = ConflictMode::parse(
+            &std::env::var("RANSOMEYE_INGEST_IDENTITY_CONFLICTS").unwrap_or_else(|_| "quarantine".to_string()),
+        )?;
+        let window_secs = match std::env::var("RANSOMEYE_INGEST_IDENTITY_CONFLICT_WINDOW_SECS") {
+            Ok(v) => v
+                .parse::<u64>()
+                .ok()
+                .filter(|n| *n > 0)
+                .ok_or_else(|| format!("Invalid RANSOMEYE_INGEST_IDENTITY_CONFLICT_WINDOW_SECS '{}'", v))?,
+            Err(_) => DEFAULT_WINDOW_SECS,
+        };
+        if mode == ConflictMode::Disabled {
+            warn!("Duplicate agent-id detection DISABLED (RANSOMEYE_INGEST_IDENTITY_CONFLICTS=disabled)");
+        }
+        Ok(Self::new(mode, StdDuration::from_secs(window_secs)))
+    }
+
+    pub fn new(mode: ConflictMode, window: StdDuration) -> Self {
+        Self {
+            mode,
+            window,
+            last_seen: DashMap::new(),
+            open: DashMap::new(),
+        }
+    }
+
+    /// Re-arm conflicts left open by a previous run.
+    pub fn restore(&self, conflicts: Vec<IdentityConflictRecord>) {
+        for conflict in conflicts {
+            let key = (conflict.component_type.clone(), conflict.component_identity.clone());
+            self.open.insert(key, conflict);
+        }
+    }
+
+    pub fn observe(&self, component_type: &str, component_id: &str, agent_id: Uuid, origin: &IdentityOrigin) -> IdentityDecision {
+        self.observe_at(component_type, component_id, agent_id, origin, Instant::now(), Utc::now())
+    }
+
+    pub fn observe_at(
+        &self,
+        component_type: &str,
+        component_id: &str,
+        agent_id: Uuid,
+        origin: &IdentityOrigin,
+        now: Instant,
+        wall: DateTime<Utc>,
+    ) -> IdentityDecision {
+        if self.mode == ConflictMode::Disabled {
+            return IdentityDecision::Accepted;
+        }
+        let key = (component_type.to_string(), component_id.to_string());
+        if let Some(conflict) = self.open.get(&key) {
+            return self.held(conflict.conflict_id);
+        }
+
+        let first_origin = match self.last_seen.entry(key.clone()) {
+            Entry::Vacant(slot) => {
+                slot.insert(SeenOrigin { origin: origin.clone(), last_seen: now });
+                return IdentityDecision::Accepted;
+            }
+            Entry::Occupied(mut slot) => {
+                let seen = slot.get_mut();
+                if !origins_conflict(&seen.origin, origin) {
+                    // Same origin; learn attributes the earlier events did not carry
+                    seen.origin.source_ip = seen.origin.source_ip.or(origin.source_ip);
+                    if seen.origin.host_id.is_none() {
+                        seen.origin.host_id = origin.host_id.clone();
+                    }
+                    seen.last_seen = now;
+                    return IdentityDecision::Accepted;
+                }
+                if now.saturating_duration_since(seen.last_seen) >= self.window {
+                    // Old origin went quiet for a full window: the component moved
+                    *seen = SeenOrigin { origin: origin.clone(), last_seen: now };
+                    return IdentityDecision::Accepted;
+                }
+                seen.origin.clone()
+            }
+        };
+
+        match self.open.entry(key) {
+            // A concurrent event opened it first
+            Entry::Occupied(existing) => self.held(existing.get().conflict_id),
+            Entry::Vacant(slot) => {
+                let conflict = IdentityConflictRecord {
+                    conflict_id: Uuid::new_v4(),
+                    component_type: component_type.to_string(),
+                    component_identity: component_id.to_string(),
+                    agent_id,
+                    first_origin,
+                    conflicting_origin: origin.clone(),
+                    opened_at: wall,
+                };
+                slot.insert(conflict.clone());
+                IdentityDecision::Opened(conflict)
+            }
+        }
+    }
+
+    fn held(&self, conflict_id: Uuid) -> IdentityDecision {
+        match self.mode {
+            ConflictMode::Quarantine => IdentityDecision::Quarantined(conflict_id),
+            _ => IdentityDecision::Accepted,
+        }
+    }
+
+    pub fn get(&self, conflict_id: Uuid) -> Option<IdentityConflictRecord> {
+        self.open.iter().find(|c| c.conflict_id == conflict_id).map(|c| c.value().clone())
+    }
+
+    /// Open conflicts, oldest first.
+    pub fn open_conflicts(&self) -> Vec<IdentityConflictRecord> {
+        let mut conflicts: Vec<IdentityConflictRecord> = self.open.iter().map(|c| c.value().clone()).collect();
+        conflicts.sort_by_key(|c| c.opened_at);
+        conflicts
+    }
+
+    /// Lift quarantine for a conflict. The identity's origin is learned afresh from its next event,
+    /// so a still-duplicated identity opens a new conflict.
+    pub fn resolve(&self, conflict_id: Uuid) -> Option<IdentityConflictRecord> {
+        let key = self
+            .open
+            .iter()
+            .find(|c| c.conflict_id == conflict_id)
+            .map(|c| c.key().clone())?;
+        let (_, conflict) = self.open.remove(&key)?;
+        self.last_seen.remove(&key);
+        Some(conflict)
+    }
+}
@@ -0,0 +1,273 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/key_pinning.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Historical agent identity binding - pins each agent's signing key (signer_id) on first use or enrollment, with operator-approved rotation
+
+/*
+ * Agent Key Pinning
+ *
+ * The first signing key an agent presents (or the one registered at enrollment) is pinned in
+ * agent_key_pins. Later events from the same agent must carry the pinned signer_id. A different
+ * key is a mismatch: reported once per (agent, key) as a detection, and rejected in enforce mode.
+ *
+ * Keys change only through operator-approved rotation (POST /agents/key/rotate). The replaced key
+ * stays valid for a grace window so in-flight and spooled events still verify.
+ *
+ * Pins are cached for PIN_CACHE_TTL; a mismatch always re-reads the row so a rotation approved
+ * on another ingest instance is honoured before anything is reported.
+ */
+
+use std::sync::Arc;
+use std::time::{Duration as StdDuration, Instant};
+
+use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+use serde::Serialize;
+use tokio_postgres::Client;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// How long a matching pin is trusted from cache before it is re-read.
+const PIN_CACHE_TTL: StdDuration = StdDuration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyPinMode {
+    /// Mismatched keys are rejected (403) and reported (default, fail-closed).
+    Enforce,
+    /// Mismatched keys are reported but the event is accepted.
+    Detect,
+    /// No pinning (lab mode without a Postgres control plane).
+    Disabled,
+}
+
+impl KeyPinMode {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "enforce" => Ok(KeyPinMode::Enforce),
+            "detect" => Ok(KeyPinMode::Detect),
+            "disabled" => Ok(KeyPinMode::Disabled),
+            other => Err(format!("Invalid RANSOMEYE_INGEST_KEY_PINNING '{}' (expected enforce|detect|disabled)", other)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KeyPinMode::Enforce => "enforce",
+            KeyPinMode::Detect => "detect",
+            KeyPinMode::Disabled => "disabled",
+        }
+    }
+}
+
+/// Pinned key of one agent (agent_key_pins row).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AgentKeyPin {
+    pub signer_id: String,
+    /// Key replaced by the last approved rotation, accepted until `previous_valid_until`
+    pub previous_signer_id: Option<String>,
+    pub previous_valid_until: Option<DateTime<Utc>>,
+}
+
+impl AgentKeyPin {
+    pub fn accepts(&self, signer_id: &str, now: DateTime<Utc>) -> bool {
+        if self.signer_id == signer_id {
+            return true;
+        }
+        match (&self.previous_signer_id, self.previous_valid_until) {
+            (Some(previous), Some(until)) => previous == signer_id && now < until,
+            _ => false,
+        }
+    }
+}
+
+/// Unexpected key presented by a pinned agent.
+#[derive(Debug, Clone)]
+pub struct KeyMismatch {
+    pub agent_id: Uuid,
+    pub pinned_signer_id: String,
+    pub presented_signer_id: String,
+    /// True the first time this process sees this (agent, key) pair.
+    pub first_report: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum PinDecision {
+    /// Pinning disabled or key matches the pin.
+    Accepted,
+    /// No pin existed; this key is now pinned.
+    PinnedFirstUse,
+    Mismatch(KeyMismatch),
+}
+
+struct CachedPin {
+    pin: AgentKeyPin,
+    loaded: Instant,
+}
+
+pub struct KeyPinning {
+    pub mode: KeyPinMode,
+    db: Option<Arc<Client>>,
+    pub rotation_grace: Duration,
+    cache: DashMap<Uuid, CachedPin>,
+    reported: DashMap<(Uuid, String), ()>,
+}
+
+impl KeyPinning {
+    /// Load from environment (FAIL-CLOSED: pinning without the Postgres control plane aborts startup).
+    pub fn from_env(db: Option<Arc<Client>>) -> Result<Self, String> {
+        let mode = KeyPinMode::parse(
+            &std::env::var("RANSOMEYE_INGEST_KEY_PINNING").unwrap_or_else(|_| "enforce".to_string()),
+        )?;
+        let grace_secs = match std::env::var("RANSOMEYE_INGEST_KEY_ROTATION_GRACE_SECS") {
+            Ok(v) => v
+                .parse::<i64>()
+                .ok()
+                .filter(|n| *n >= 0)
+                .ok_or_else(|| format!("Invalid RANSOMEYE_INGEST_KEY_ROTATION_GRACE_SECS '{}'", v))?,
+            Err(_) => 3600,
+        };
+
+        if mode != KeyPinMode::Disabled && db.is_none() {
+            return Err(
+                "FAIL-CLOSED: agent key pinning requires postgres backend; set RANSOMEYE_INGEST_KEY_PINNING=disabled for lab mode"
+                    .to_string(),
+            );
+        }
+        if mode == KeyPinMode::Disabled {
+            warn!("Agent key pinning DISABLED (RANSOMEYE_INGEST_KEY_PINNING=disabled)");
+        }
+
+        Ok(Self::new(mode, db, Duration::seconds(grace_secs)))
+    }
+
+    pub fn new(mode: KeyPinMode, db: Option<Arc<Client>>, rotation_grace: Duration) -> Self {
+        Self {
+            mode,
+            db,
+            rotation_grace,
+            cache: DashMap::new(),
+            reported: DashMap::new(),
+        }
+    }
+
+    /// Check `signer_id` against the agent's pin, pinning it if the agent has none yet.
+    pub async fn check(&self, agent_id: Uuid, signer_id: &str) -> Result<PinDecision, String> {
+        if self.mode == KeyPinMode::Disabled {
+            return Ok(PinDecision::Accepted);
+        }
+        let now = Utc::now();
+        if let Some(cached) = self.cache.get(&agent_id) {
+            if cached.loaded.elapsed() < PIN_CACHE_TTL && cached.pin.accepts(signer_id, now) {
+                return Ok(PinDecision::Accepted);
+            }
+        }
+
+        let db = self.db()?;
+        let (pin, first_use) = match load_pin(db, agent_id).await? {
+            Some(pin) => (pin, false),
+            None => match insert_pin(db, agent_id, signer_id, "first_use").await? {
+                Some(pin) => (pin, true),
+                // Lost a race with a concurrent first use; the winner's pin decides
+                None => (load_pin(db, agent_id).await?.ok_or("agent_key_pins row vanished")?, false),
+            },
+        };
+        self.cache.insert(agent_id, CachedPin { pin: pin.clone(), loaded: Instant::now() });
+
+        if pin.accepts(signer_id, now) {
+            if first_use {
+                info!("Agent key pinned on first use | agent_id={} | signer_id={}", agent_id, signer_id);
+                return Ok(PinDecision::PinnedFirstUse);
+            }
+            return Ok(PinDecision::Accepted);
+        }
+
+        let first_report = self.reported.insert((agent_id, signer_id.to_string()), ()).is_none();
+        Ok(PinDecision::Mismatch(KeyMismatch {
+            agent_id,
+            pinned_signer_id: pin.signer_id,
+            presented_signer_id: signer_id.to_string(),
+            first_report,
+        }))
+    }
+
+    /// Pin the key registered at enrollment. Re-enrolling with the same key is a no-op;
+    /// a different key is refused (`Ok(Some(existing))`) and must go through rotation.
+    pub async fn pin_at_enrollment(&self, agent_id: Uuid, signer_id: &str) -> Result<Option<AgentKeyPin>, String> {
+        let db = self.db()?;
+        if insert_pin(db, agent_id, signer_id, "enrollment").await?.is_some() {
+            return Ok(None);
+        }
+        let existing = load_pin(db, agent_id).await?.ok_or("agent_key_pins row vanished")?;
+        Ok(if existing.signer_id == signer_id { None } else { Some(existing) })
+    }
+
+    /// Operator-approved rotation: pin `new_signer_id`, keep the replaced key valid for the grace window.
+    pub async fn approve_rotation(&self, agent_id: Uuid, new_signer_id: &str, reason: &str) -> Result<AgentKeyPin, String> {
+        let db = self.db()?;
+        let grace_until = Utc::now() + self.rotation_grace;
+        let row = db
+            .query_one(
+                r#"
+                INSERT INTO agent_key_pins (agent_id, signer_id, pinned_via, rotation_reason)
+                VALUES ($1, $2, 'operator_rotation', $4)
+                ON CONFLICT (agent_id) DO UPDATE SET
+                    previous_signer_id = CASE WHEN agent_key_pins.signer_id = EXCLUDED.signer_id
+                                              THEN agent_key_pins.previous_signer_id ELSE agent_key_pins.signer_id END,
+                    previous_valid_until = CASE WHEN agent_key_pins.signer_id = EXCLUDED.signer_id
+                                                THEN agent_key_pins.previous_valid_until ELSE $3 END,
+                    signer_id = EXCLUDED.signer_id,
+                    pinned_via = 'operator_rotation',
+                    rotation_reason = EXCLUDED.rotation_reason,
+                    updated_at = NOW()
+                RETURNING signer_id, previous_signer_id, previous_valid_until
+                "#,
+                &[&agent_id, &new_signer_id, &grace_until, &reason],
+            )
+            .await
+            .map_err(|e| format!("agent_key_pins rotation failed: {}", e))?;
+        let pin = pin_from_row(&row);
+
+        self.cache.remove(&agent_id);
+        self.reported.retain(|(id, _), _| *id != agent_id);
+        Ok(pin)
+    }
+
+    fn db(&self) -> Result<&Arc<Client>, String> {
+        self.db.as_ref().ok_or_else(|| "agent key pinning requires postgres backend".to_string())
+    }
+}
+
+async fn load_pin(db: &Client, agent_id: Uuid) -> Result<Option<AgentKeyPin>, String> {
+    let row = db
+        .query_opt(
+            "SELECT signer_id, previous_signer_id, previous_valid_until FROM agent_key_pins WHERE agent_id = $1",
+            &[&agent_id],
+        )
+        .await
+        .map_err(|e| format!("agent_key_pins lookup failed: {}", e))?;
+    Ok(row.as_ref().map(pin_from_row))
+}
+
+/// Insert a pin if the agent has none; `None` when a pin already exists.
+async fn insert_pin(db: &Client, agent_id: Uuid, signer_id: &str, pinned_via: &str) -> Result<Option<AgentKeyPin>, String> {
+    let row = db
+        .query_opt(
+            r#"
+            INSERT INTO agent_key_pins (agent_id, signer_id, pinned_via)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (agent_id) DO NOTHING
+            RETURNING signer_id, previous_signer_id, previous_valid_until
+            "#,
+            &[&agent_id, &signer_id, &pinned_via],
+        )
+        .await
+        .map_err(|e| format!("agent_key_pins insert failed: {}", e))?;
+    Ok(row.as_ref().map(pin_from_row))
+}
+
+fn pin_from_row(row: &tokio_postgres::Row) -> AgentKeyPin {
+    AgentKeyPin {
+        signer_id: row.get(0),
+        previous_signer_id: row.get(1),
+        previous_valid_until: row.get(2),
+    }
+}
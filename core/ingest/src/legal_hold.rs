@@ -0,0 +1,76 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/legal_hold.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Legal holds - held subject types (incident, entity), the hold record and the rule linking table columns to held subjects, shared by the admin API and the retention enforcer
+
+/*
+ * Legal Holds
+ *
+ * An operator places a hold on an incident or an entity (POST /admin/legal-holds). While the
+ * hold is active (legal_holds.released_at IS NULL), retention must not delete anything linked
+ * to the subject:
+ *
+ *   incident  rows whose uuid column incident_id equals the held id
+ *   entity    rows whose uuid column entity_id or *_entity_id (primary_entity_id,
+ *             target_entity_id, ...) equals the held id, including the entities row itself
+ *
+ * The link is by column name so new tables are covered without registering them. Releasing a
+ * hold (POST /admin/legal-holds/release) keeps the row as history; the next retention run
+ * purges what is past its policy. Holds need the Postgres control plane.
+ */
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// legal_holds.subject_type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum HoldSubject {
+    Incident,
+    Entity,
+}
+
+impl HoldSubject {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HoldSubject::Incident => "incident",
+            HoldSubject::Entity => "entity",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "incident" => Some(HoldSubject::Incident),
+            "entity" => Some(HoldSubject::Entity),
+            _ => None,
+        }
+    }
+
+    /// Subject type a uuid column links to, if any (see module docs).
+    pub fn linked_by_column(column: &str) -> Option<Self> {
+        if column == "incident_id" {
+            Some(HoldSubject::Incident)
+        } else if column == "entity_id" || column.ends_with("_entity_id") {
+            Some(HoldSubject::Entity)
+        } else {
+            None
+        }
+    }
+}
+
+/// One hold, active or released.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LegalHold {
+    pub hold_id: Uuid,
+    pub subject_type: HoldSubject,
+    pub subject_id: Uuid,
+    pub reason: String,
+    pub placed_by: String,
+    pub placed_at: DateTime<Utc>,
+    pub released_by: Option<String>,
+    pub release_reason: Option<String>,
+    pub released_at: Option<DateTime<Utc>>,
+    /// Not yet released (filter[active]=true lists what retention currently skips)
+    pub active: bool,
+}
@@ -2,21 +2,94 @@
 // Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
 // Details of functionality of this file: Library exports for testing
 
+pub mod agent_cache;
+pub mod agent_log;
+pub mod annotation;
+pub mod attack;
+pub mod audit_review;
+pub mod agent_token;
 pub mod auth;
 pub mod backpressure;
 pub mod buffer;
+pub mod component_budget;
 pub mod config;
+pub mod crash_report;
+pub mod dashboard_cache;
 pub mod dedupe;
 pub mod dispatcher;
+pub mod disk_quota;
+pub mod drop_accounting;
+pub mod envelope_validation;
+pub mod export;
+pub mod feature_flags;
+pub mod handoff;
+pub mod host_inventory;
+pub mod http_agent_auth;
+pub mod http_agent_log;
+pub mod http_annotation_admin;
+pub mod http_audit_review_admin;
+pub mod http_dashboard_admin;
+pub mod http_drops_admin;
+pub mod http_export_admin;
+pub mod http_feature_flags_admin;
+pub mod http_fleet_admin;
+pub mod http_identity_admin;
+pub mod http_legal_hold_admin;
+pub mod http_list;
+pub mod http_maintenance_admin;
+pub mod http_memory_acquisition;
+pub mod http_operator_auth;
+pub mod http_pcap_capture;
+pub mod http_retention_admin;
+pub mod http_rule_metrics_admin;
+pub mod http_runtime_admin;
+pub mod http_sandbox;
+pub mod http_schema_admin;
+pub mod http_server;
+pub mod http_suppression_admin;
+pub mod http_webhook_admin;
+pub mod http_yara;
+pub mod identity_conflict;
+pub mod key_pinning;
+pub mod legal_hold;
+pub mod lineage;
 pub mod listener;
+pub mod load_shedding;
+pub mod maintenance;
+pub mod memory_acquisition;
 pub mod normalization;
+pub mod oidc;
+pub mod openapi;
+pub mod operator_auth;
 pub mod ordering;
+pub mod outbox;
+pub mod payload_policy;
+pub mod pcap_capture;
+pub mod pipeline;
 pub mod protocol;
 pub mod rate_limit;
+pub mod residency;
+pub mod retention_runs;
+pub mod rule_metrics;
+pub mod runtime_controls;
+pub mod sandbox;
 pub mod schema;
+pub mod schema_compat;
 pub mod security;
+pub mod service_health;
+pub mod service_heartbeat;
+pub mod shared_state;
 pub mod signature;
+pub mod signature_verification;
+pub mod storage;
+pub mod suppression;
+pub mod topology;
 pub mod versioning;
+pub mod webhooks;
+pub mod yara_rules;
 
 pub use protocol::event_envelope::EventEnvelope;
 
+
+/// Build provenance of this crate (commit, Cargo.lock hash, SBOM); see build.rs
+pub static BUILD_INFO: build_info::BuildInfo = build_info::build_info!();
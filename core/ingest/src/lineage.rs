@@ -0,0 +1,285 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/lineage.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Process lineage verification - recompute each Linux process event's lineage hash and follow its parent link per (agent, boot, chain) to detect altered, missing or injected process events
+
+/*
+ * Process Lineage Verification
+ *
+ * The Linux agent links every process event into a per-boot hash chain (agent lineage.rs):
+ *   hash        = SHA-256("ransomeye-lineage-v1" | boot_id | event_type | pid | ppid | uid | gid |
+ *                         executable | command_line | mmap_address | mmap_size | parent_hash)
+ *   parent_hash = hash of the latest event of the process it descends from, or the genesis
+ *                 SHA-256("ransomeye-lineage-genesis-v1" | boot_id | chain_id)
+ * (fields joined with 0x1f, absent values empty).
+ *
+ * Ingest checks each event of a stream (agent, boot_id, chain_id):
+ *   tampered_event  the recomputed hash differs from the carried one: the event was altered
+ *                   after the agent linked it, or fabricated without knowing the scheme
+ *   broken_link     parent_hash is neither the genesis nor a hash seen in the stream within
+ *                   the grace window (RANSOMEYE_INGEST_LINEAGE_GRACE_SECS, default 300; spooled
+ *                   and priority-queued events arrive out of order): a process event is
+ *                   missing, or this one was injected with an invented parent
+ *
+ * Broken links can only be judged in streams observed from their genesis. A stream first seen
+ * mid-chain (ingest restarted while the agent kept running) adopts unknown parents and is only
+ * checked for tampered events until the agent's next chain. Findings are detections; the event
+ * itself is still stored. Grace expiry is evaluated when the stream's next event arrives.
+ * RANSOMEYE_INGEST_LINEAGE=detect (default) | disabled.
+ */
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration as StdDuration, Instant};
+
+use dashmap::DashMap;
+use crypto::digest::Sha256;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::protocol::signed_event::{LinuxLineage, LinuxProcessData};
+
+const DEFAULT_GRACE_SECS: u64 = 300;
+/// Hashes remembered per stream (older parents are treated as unknown)
+const MAX_KNOWN_HASHES: usize = 100_000;
+/// Unresolved links waiting for their parent per stream
+const MAX_PENDING_LINKS: usize = 10_000;
+/// Streams without events for this long are forgotten
+const STREAM_IDLE_TTL: StdDuration = StdDuration::from_secs(24 * 3600);
+const SEP: &str = "\x1f";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineageMode {
+    /// Verify chains and report findings as detections (default).
+    Detect,
+    /// No lineage verification.
+    Disabled,
+}
+
+impl LineageMode {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "detect" => Ok(LineageMode::Detect),
+            "disabled" => Ok(LineageMode::Disabled),
+            other => Err(format!("Invalid RANSOMEYE_INGEST_LINEAGE '{}' (expected detect|disabled)", other)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineageMode::Detect => "detect",
+            LineageMode::Disabled => "disabled",
+        }
+    }
+}
+
+/// Hashed fields of one process event, as extracted from the envelope.
+#[derive(Debug, Clone, Copy)]
+pub struct LineageEvent<'a> {
+    pub event_type: &'a str,
+    pub pid: u64,
+    pub ppid: Option<u64>,
+    pub uid: u64,
+    pub gid: u64,
+    pub executable: Option<&'a str>,
+    pub command_line: Option<&'a str>,
+    pub mmap_address: Option<u64>,
+    pub mmap_size: Option<u64>,
+}
+
+impl<'a> LineageEvent<'a> {
+    /// Fields of a Linux agent process event (absent ids hash as 0, as the agent never omits them)
+    pub fn from_linux(pid: Option<u64>, uid: Option<u64>, gid: Option<u64>, process: &'a LinuxProcessData) -> Self {
+        Self {
+            event_type: process.event_type.as_deref().unwrap_or_default(),
+            pid: pid.unwrap_or_default(),
+            ppid: process.ppid,
+            uid: uid.unwrap_or_default(),
+            gid: gid.unwrap_or_default(),
+            executable: process.executable.as_deref(),
+            command_line: process.command_line.as_deref(),
+            mmap_address: process.mmap_address,
+            mmap_size: process.mmap_size,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LineageFinding {
+    /// Carried hash does not match the event's fields and parent
+    TamperedEvent { boot_id: String, chain_id: String, hash: String, expected_hash: String },
+    /// No event with `parent_hash` arrived within the grace window
+    BrokenLink { boot_id: String, chain_id: String, parent_hash: String, child_hash: String },
+}
+
+impl LineageFinding {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            LineageFinding::TamperedEvent { .. } => "tampered_event",
+            LineageFinding::BrokenLink { .. } => "broken_link",
+        }
+    }
+}
+
+pub fn genesis_hash(boot_id: &str, chain_id: &str) -> String {
+    hex::encode(Sha256::digest(["ransomeye-lineage-genesis-v1", boot_id, chain_id].join(SEP).as_bytes()))
+}
+
+pub fn lineage_hash(boot_id: &str, event: &LineageEvent<'_>, parent_hash: &str) -> String {
+    let opt = |v: Option<String>| v.unwrap_or_default();
+    let fields = [
+        "ransomeye-lineage-v1".to_string(),
+        boot_id.to_string(),
+        event.event_type.to_string(),
+        event.pid.to_string(),
+        opt(event.ppid.map(|v| v.to_string())),
+        event.uid.to_string(),
+        event.gid.to_string(),
+        opt(event.executable.map(str::to_string)),
+        opt(event.command_line.map(str::to_string)),
+        opt(event.mmap_address.map(|v| v.to_string())),
+        opt(event.mmap_size.map(|v| v.to_string())),
+        parent_hash.to_string(),
+    ];
+    hex::encode(Sha256::digest(fields.join(SEP).as_bytes()))
+}
+
+struct PendingLink {
+    child_hash: String,
+    since: Instant,
+}
+
+struct Stream {
+    genesis: String,
+    /// Observed from its genesis: unknown parents are broken links
+    anchored: bool,
+    known: HashSet<String>,
+    known_order: VecDeque<String>,
+    /// parent_hash -> children still waiting for it
+    pending: HashMap<String, Vec<PendingLink>>,
+    pending_count: usize,
+    last_seen: Instant,
+}
+
+impl Stream {
+    fn remember(&mut self, hash: &str) {
+        if self.known.insert(hash.to_string()) {
+            self.known_order.push_back(hash.to_string());
+            if self.known_order.len() > MAX_KNOWN_HASHES {
+                if let Some(oldest) = self.known_order.pop_front() {
+                    self.known.remove(&oldest);
+                }
+            }
+        }
+    }
+}
+
+type StreamKey = (Uuid, String, String);
+
+pub struct LineageVerifier {
+    pub mode: LineageMode,
+    grace: StdDuration,
+    streams: DashMap<StreamKey, Stream>,
+}
+
+impl LineageVerifier {
+    pub fn from_env() -> Result<Self, String> {
+        let mode = LineageMode::parse(&std::env::var("RANSOMEYE_INGEST_LINEAGE").unwrap_or_else(|_| "detect".to_string()))?;
+        let grace_secs = match std::env::var("RANSOMEYE_INGEST_LINEAGE_GRACE_SECS") {
+            Ok(v) => v
+                .parse::<u64>()
+                .ok()
+                .filter(|n| *n > 0)
+                .ok_or_else(|| format!("Invalid RANSOMEYE_INGEST_LINEAGE_GRACE_SECS '{}'", v))?,
+            Err(_) => DEFAULT_GRACE_SECS,
+        };
+        if mode == LineageMode::Disabled {
+            warn!("Process lineage verification DISABLED (RANSOMEYE_INGEST_LINEAGE=disabled)");
+        }
+        Ok(Self::new(mode, StdDuration::from_secs(grace_secs)))
+    }
+
+    pub fn new(mode: LineageMode, grace: StdDuration) -> Self {
+        Self { mode, grace, streams: DashMap::new() }
+    }
+
+    pub fn observe(&self, agent_id: Uuid, event: &LineageEvent<'_>, link: &LinuxLineage) -> Vec<LineageFinding> {
+        self.observe_at(agent_id, event, link, Instant::now())
+    }
+
+    pub fn observe_at(&self, agent_id: Uuid, event: &LineageEvent<'_>, link: &LinuxLineage, now: Instant) -> Vec<LineageFinding> {
+        if self.mode == LineageMode::Disabled {
+            return Vec::new();
+        }
+        let expected_hash = lineage_hash(&link.boot_id, event, &link.parent_hash);
+        if expected_hash != link.hash {
+            // Not remembered: children of a tampered event are broken links too
+            return vec![LineageFinding::TamperedEvent {
+                boot_id: link.boot_id.clone(),
+                chain_id: link.chain_id.clone(),
+                hash: link.hash.clone(),
+                expected_hash,
+            }];
+        }
+
+        let key = (agent_id, link.boot_id.clone(), link.chain_id.clone());
+        if !self.streams.contains_key(&key) {
+            self.streams.retain(|_, s| now.saturating_duration_since(s.last_seen) < STREAM_IDLE_TTL);
+        }
+        let mut stream = self.streams.entry(key).or_insert_with(|| {
+            let genesis = genesis_hash(&link.boot_id, &link.chain_id);
+            Stream {
+                anchored: link.parent_hash == genesis,
+                genesis,
+                known: HashSet::new(),
+                known_order: VecDeque::new(),
+                pending: HashMap::new(),
+                pending_count: 0,
+                last_seen: now,
+            }
+        });
+        stream.last_seen = now;
+
+        // This event may be the parent earlier children were waiting for
+        if let Some(resolved) = stream.pending.remove(&link.hash) {
+            stream.pending_count -= resolved.len();
+        }
+        stream.remember(&link.hash);
+
+        if link.parent_hash != stream.genesis && !stream.known.contains(&link.parent_hash) {
+            if !stream.anchored {
+                stream.remember(&link.parent_hash);
+            } else if stream.pending_count < MAX_PENDING_LINKS {
+                stream.pending_count += 1;
+                stream
+                    .pending
+                    .entry(link.parent_hash.clone())
+                    .or_default()
+                    .push(PendingLink { child_hash: link.hash.clone(), since: now });
+            } else {
+                warn!("Lineage stream {} {} has {} unresolved links; not tracking more", agent_id, link.chain_id, MAX_PENDING_LINKS);
+            }
+        }
+
+        // Links whose parent never arrived within the grace window
+        let grace = self.grace;
+        let mut findings = Vec::new();
+        let mut expired = 0;
+        stream.pending.retain(|parent_hash, children| {
+            children.retain(|child| {
+                if now.saturating_duration_since(child.since) < grace {
+                    return true;
+                }
+                findings.push(LineageFinding::BrokenLink {
+                    boot_id: link.boot_id.clone(),
+                    chain_id: link.chain_id.clone(),
+                    parent_hash: parent_hash.clone(),
+                    child_hash: child.child_hash.clone(),
+                });
+                expired += 1;
+                false
+            });
+            !children.is_empty()
+        });
+        stream.pending_count -= expired;
+        findings
+    }
+}
@@ -0,0 +1,313 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/load_shedding.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Ingest load shedding under database degradation - watches unit-of-work latency and pipeline queue depth, then accepts low-priority events and slow agents with relaxed durability or refuses them with 503 and Retry-After while critical events keep flowing
+
+/*
+ * Load Shedding
+ *
+ * When the database slows down every request waits on its shard queue until the agent times
+ * out, detections included. The load shedder decides per event, before it is queued, from the
+ * pipeline's moving-average unit-of-work latency and queue depths (pipeline.rs):
+ *
+ *   - shedding starts when the latency reaches RANSOMEYE_INGEST_LOAD_SHED_LATENCY_MS or the
+ *     pipeline is RANSOMEYE_INGEST_LOAD_SHED_QUEUE_PCT full, and stops once both are below half
+ *     of that (no flapping around one threshold);
+ *   - while shedding, low-priority events (agent_stats, host_inventory, routine file and network
+ *     activity, DPI flows) are shed;
+ *   - an agent whose shard queue is RANSOMEYE_INGEST_LOAD_SHED_QUEUE_PCT full is shed for
+ *     everything but critical events, shedding or not, so one noisy agent cannot fill its shard;
+ *   - critical events (detections, canaries, YARA matches, mass writes) are never shed.
+ *
+ * RANSOMEYE_INGEST_LOAD_SHED selects what shedding means:
+ *
+ *   off     never shed (default; a full shard queue still refuses with 503)
+ *   reject  refuse with 503 and Retry-After (RANSOMEYE_INGEST_LOAD_SHED_RETRY_AFTER_SECS); the
+ *           agent keeps the event and retries
+ *   wal     accept, committing with synchronous_commit off: the commit is acknowledged once
+ *           the WAL record is written, without waiting for the flush. A database crash can lose
+ *           the last shed events, never corrupt them; critical events keep full durability
+ *
+ * Every 503 of the ingest routes carries Retry-After, including a full shard queue.
+ */
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::Serialize;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::pipeline::PipelineLoad;
+use crate::storage::TelemetrySource;
+
+const DEFAULT_LATENCY_MS: u64 = 250;
+const DEFAULT_QUEUE_PCT: u64 = 50;
+const DEFAULT_RETRY_AFTER_SECS: u64 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadShedMode {
+    /// Never shed
+    Off,
+    /// Refuse shed events with 503 and Retry-After
+    Reject,
+    /// Accept shed events with relaxed commit durability
+    Wal,
+}
+
+impl LoadShedMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LoadShedMode::Off => "off",
+            LoadShedMode::Reject => "reject",
+            LoadShedMode::Wal => "wal",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadShedConfig {
+    pub mode: LoadShedMode,
+    /// Average unit-of-work duration that starts shedding
+    pub latency: Duration,
+    /// Queue fill (percent of capacity) that starts shedding, and marks an agent's shard as slow
+    pub queue_pct: u64,
+    /// Retry-After of a 503 from the ingest routes
+    pub retry_after_secs: u64,
+}
+
+fn env_u64(key: &str, default_value: u64, max: u64) -> Result<u64, String> {
+    match std::env::var(key) {
+        Ok(v) => v
+            .parse::<u64>()
+            .ok()
+            .filter(|n| (1..=max).contains(n))
+            .ok_or_else(|| format!("Invalid {} '{}' (expected integer in 1..={})", key, v, max)),
+        Err(_) => Ok(default_value),
+    }
+}
+
+impl LoadShedConfig {
+    /// No shedding (default, and the behaviour before load shedding existed).
+    pub fn off() -> Self {
+        Self {
+            mode: LoadShedMode::Off,
+            latency: Duration::from_millis(DEFAULT_LATENCY_MS),
+            queue_pct: DEFAULT_QUEUE_PCT,
+            retry_after_secs: DEFAULT_RETRY_AFTER_SECS,
+        }
+    }
+
+    pub fn from_env() -> Result<Self, String> {
+        let mode = match std::env::var("RANSOMEYE_INGEST_LOAD_SHED").as_deref() {
+            Ok("off") | Err(_) => LoadShedMode::Off,
+            Ok("reject") => LoadShedMode::Reject,
+            Ok("wal") => LoadShedMode::Wal,
+            Ok(other) => {
+                return Err(format!("Invalid RANSOMEYE_INGEST_LOAD_SHED '{}' (expected off|reject|wal)", other));
+            }
+        };
+        Ok(Self {
+            mode,
+            latency: Duration::from_millis(env_u64("RANSOMEYE_INGEST_LOAD_SHED_LATENCY_MS", DEFAULT_LATENCY_MS, 60_000)?),
+            queue_pct: env_u64("RANSOMEYE_INGEST_LOAD_SHED_QUEUE_PCT", DEFAULT_QUEUE_PCT, 100)?,
+            retry_after_secs: env_u64("RANSOMEYE_INGEST_LOAD_SHED_RETRY_AFTER_SECS", DEFAULT_RETRY_AFTER_SECS, 3600)?,
+        })
+    }
+}
+
+/// How much an event matters when the database cannot keep up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShedPriority {
+    /// Never shed
+    Critical,
+    /// Shed only when its agent is slow
+    Standard,
+    /// Shed while shedding, and when its agent is slow
+    Low,
+}
+
+impl ShedPriority {
+    /// Priority from the facts the ingest handler has already extracted.
+    pub fn classify(source: TelemetrySource, event_category: Option<&str>, event_type: Option<&str>) -> Self {
+        match source {
+            TelemetrySource::LinuxAgent => match (event_category, event_type) {
+                (Some("detection" | "canary" | "yara_match"), _) | (_, Some("MassWrite")) => ShedPriority::Critical,
+                (_, Some("Exec")) => ShedPriority::Standard,
+                _ => ShedPriority::Low,
+            },
+            TelemetrySource::DpiProbe => ShedPriority::Low,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ShedPriority::Critical => "critical",
+            ShedPriority::Standard => "standard",
+            ShedPriority::Low => "low",
+        }
+    }
+}
+
+/// Decision for one event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admission {
+    Admit,
+    /// Accept; commit with relaxed durability (StorageTx::relax_durability)
+    Relaxed,
+    /// Refuse with 503 and Retry-After
+    Reject,
+}
+
+/// Shedding state and counters since start (GET /admin/load-shedding).
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LoadShedStats {
+    /// off | reject | wal
+    pub mode: String,
+    pub latency_threshold_ms: u64,
+    pub queue_pct: u64,
+    pub retry_after_secs: u64,
+    pub shedding: bool,
+    /// Start of the current shedding period
+    pub shedding_since: Option<DateTime<Utc>>,
+    /// Shedding periods started
+    pub episodes: u64,
+    /// Low-priority events accepted with relaxed durability while shedding
+    pub relaxed_low_priority: u64,
+    /// Events of slow agents accepted with relaxed durability
+    pub relaxed_slow_agent: u64,
+    /// Low-priority events refused while shedding
+    pub rejected_low_priority: u64,
+    /// Events of slow agents refused
+    pub rejected_slow_agent: u64,
+}
+
+pub struct LoadShedder {
+    cfg: LoadShedConfig,
+    /// Start of the current shedding period
+    shedding: Mutex<Option<(Instant, DateTime<Utc>)>>,
+    episodes: AtomicU64,
+    relaxed_low_priority: AtomicU64,
+    relaxed_slow_agent: AtomicU64,
+    rejected_low_priority: AtomicU64,
+    rejected_slow_agent: AtomicU64,
+}
+
+impl LoadShedder {
+    pub fn new(cfg: LoadShedConfig) -> Self {
+        Self {
+            cfg,
+            shedding: Mutex::new(None),
+            episodes: AtomicU64::new(0),
+            relaxed_low_priority: AtomicU64::new(0),
+            relaxed_slow_agent: AtomicU64::new(0),
+            rejected_low_priority: AtomicU64::new(0),
+            rejected_slow_agent: AtomicU64::new(0),
+        }
+    }
+
+    pub fn config(&self) -> &LoadShedConfig {
+        &self.cfg
+    }
+
+    pub fn is_shedding(&self) -> bool {
+        self.shedding.lock().is_some()
+    }
+
+    /// Decide for an event of `priority` given the load its agent's shard and the pipeline are under.
+    pub fn admit(&self, priority: ShedPriority, load: &PipelineLoad) -> Admission {
+        if self.cfg.mode == LoadShedMode::Off {
+            return Admission::Admit;
+        }
+        let shedding = self.update(load);
+        if priority == ShedPriority::Critical {
+            return Admission::Admit;
+        }
+        let (relaxed, rejected) = if load.shard_fill_pct() >= self.cfg.queue_pct {
+            (&self.relaxed_slow_agent, &self.rejected_slow_agent)
+        } else if shedding && priority == ShedPriority::Low {
+            (&self.relaxed_low_priority, &self.rejected_low_priority)
+        } else {
+            return Admission::Admit;
+        };
+        match self.cfg.mode {
+            LoadShedMode::Wal => {
+                relaxed.fetch_add(1, Ordering::Relaxed);
+                Admission::Relaxed
+            }
+            _ => {
+                rejected.fetch_add(1, Ordering::Relaxed);
+                Admission::Reject
+            }
+        }
+    }
+
+    /// Enter or leave shedding; true while shedding.
+    fn update(&self, load: &PipelineLoad) -> bool {
+        let latency = load.latency;
+        let fill = load.fill_pct();
+        let mut shedding = self.shedding.lock();
+        match *shedding {
+            None if latency >= self.cfg.latency || fill >= self.cfg.queue_pct => {
+                *shedding = Some((Instant::now(), Utc::now()));
+                self.episodes.fetch_add(1, Ordering::Relaxed);
+                warn!(
+                    "Load shedding started | mode={} | latency_ms={} | queue_fill_pct={} | queued={}",
+                    self.cfg.mode.as_str(),
+                    latency.as_millis(),
+                    fill,
+                    load.queued
+                );
+                true
+            }
+            Some((since, _)) if latency * 2 < self.cfg.latency && fill * 2 < self.cfg.queue_pct => {
+                *shedding = None;
+                info!(
+                    "Load shedding stopped after {}s | latency_ms={} | queue_fill_pct={}",
+                    since.elapsed().as_secs(),
+                    latency.as_millis(),
+                    fill
+                );
+                false
+            }
+            current => current.is_some(),
+        }
+    }
+
+    pub fn stats(&self) -> LoadShedStats {
+        LoadShedStats {
+            mode: self.cfg.mode.as_str().to_string(),
+            latency_threshold_ms: self.cfg.latency.as_millis() as u64,
+            queue_pct: self.cfg.queue_pct,
+            retry_after_secs: self.cfg.retry_after_secs,
+            shedding: self.is_shedding(),
+            shedding_since: self.shedding.lock().map(|(_, at)| at),
+            episodes: self.episodes.load(Ordering::Relaxed),
+            relaxed_low_priority: self.relaxed_low_priority.load(Ordering::Relaxed),
+            relaxed_slow_agent: self.relaxed_slow_agent.load(Ordering::Relaxed),
+            rejected_low_priority: self.rejected_low_priority.load(Ordering::Relaxed),
+            rejected_slow_agent: self.rejected_slow_agent.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Router layer on the ingest routes: a 503 tells the agent when to retry.
+pub async fn retry_after(State(shedder): State<Arc<LoadShedder>>, req: Request<Body>, next: Next) -> Response {
+    let mut response = next.run(req).await;
+    if response.status() == StatusCode::SERVICE_UNAVAILABLE {
+        response
+            .headers_mut()
+            .entry(header::RETRY_AFTER)
+            .or_insert(HeaderValue::from(shedder.cfg.retry_after_secs));
+    }
+    response
+}
@@ -11,7 +11,7 @@
 
 use std::sync::Arc;
 use tokio::signal;
-use tracing::{info, error};
+use tracing::{info, error, warn};
 
 mod server;
 mod listener;
@@ -29,16 +29,31 @@ mod config;
 mod security;
 mod protocol;
 mod dedupe;
+mod shared_state;
 
 use server::IngestionServer;
 use config::Config;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ingest::BUILD_INFO.handle_version_flag("ingest");
+
     // Initialize tracing
     tracing_subscriber::fmt::init();
+    kernel::crash::install("ransomeye-ingest", Some(ingest::crash_report::reporter()));
     
     info!("Starting RansomEye Event Ingestion Server");
+
+    // FAIL-CLOSED: a release deployment only runs the exact build its release manifest lists
+    let build = &ingest::BUILD_INFO;
+    match build.verify_release_from_env("ingest")? {
+        Some(release) => info!("Build verified against release {} | commit={}", release, build.git_commit),
+        None => warn!("RANSOMEYE_RELEASE_MANIFEST not set; build commit={} not verified against a release", build.git_commit),
+    }
+
+    // FAIL-CLOSED: the compiled-in crypto backend must pass its self-check
+    crypto::self_check()?;
+    info!("Crypto backend: {}", crypto::backend());
     
     // Load configuration
     let config = Config::load()?;
@@ -0,0 +1,578 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/maintenance.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Maintenance windows - host, zone or tenant scoped time ranges that suppress or downgrade configured detection classes, the in-transaction check applied to every detection and the post-window report of what they held back
+
+/*
+ * Maintenance Windows
+ *
+ * Patch windows produce expected noise: service restarts, reboots, software pushes. An operator
+ * opens a window (POST /admin/maintenance-windows) for a scope and a time range:
+ *
+ *   {
+ *     "scope":       { "kind": "zone", "value": "hq-servers" },
+ *     "starts_at":   "2026-10-17T22:00:00Z",
+ *     "ends_at":     "2026-10-18T02:00:00Z",
+ *     "action":      "downgrade",
+ *     "downgrade_to": "info",
+ *     "classes":     [ { "detection_engine": "ingest_key_pinning" },
+ *                      { "detection_category": "lateral_movement" } ],
+ *     "reason":      "October patch window (CHG-2291)",
+ *     "created_by":  "ops-lead"
+ *   }
+ *
+ * Scope kinds:
+ *
+ *   host    the agent's component identity (agents.host_hostname or the component_id artifact)
+ *           or its FQDN
+ *   zone    the topology zone of the agent (label of its latest zoned raw event) or a zone named
+ *           in the artifacts (zone, source_zone, destination_zone)
+ *   tenant  agents.tenant_id of the agent named in the artifacts (agent_id)
+ *
+ * A detection inside an active window (starts_at <= now < ends_at, not cancelled) that matches
+ * one of its classes is still written to detection_results with maintenance_window_id set:
+ *
+ *   suppress   no detection.created webhook and no packet capture; it raises no alert
+ *   downgrade  stored and alerted at downgrade_to, the original severity kept in
+ *              original_severity; a window never raises a severity
+ *
+ * A suppressing window wins over a downgrading one; detections already silenced by a suppression
+ * rule are left alone. Each hit is counted on the window (suppressed_count, downgraded_count),
+ * and GET /admin/maintenance-windows/report lists what a window held back once it is over.
+ * Windows are at most MAX_WINDOW_HOURS long, open at most MAX_LEAD_DAYS ahead and need the
+ * Postgres control plane.
+ */
+
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use tokio_postgres::types::Json;
+use tokio_postgres::{Client, Row};
+use tracing::error;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::storage::DetectionRecord;
+use crate::suppression::{check_text, SEVERITY_LEVELS};
+
+/// Longest a window may last
+pub const MAX_WINDOW_HOURS: i64 = 7 * 24;
+/// How far ahead a window may be scheduled
+pub const MAX_LEAD_DAYS: i64 = 90;
+const MAX_CLASSES: usize = 16;
+/// Detections listed individually in a report (newest first); the summary counts all of them
+pub const MAX_REPORTED_DETECTIONS: i64 = 500;
+
+fn severity_rank(label: &str) -> Option<usize> {
+    SEVERITY_LEVELS.iter().position(|l| *l == label)
+}
+
+/// maintenance_windows.scope_kind
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ScopeKind {
+    Host,
+    Zone,
+    Tenant,
+}
+
+impl ScopeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScopeKind::Host => "host",
+            ScopeKind::Zone => "zone",
+            ScopeKind::Tenant => "tenant",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "host" => Some(ScopeKind::Host),
+            "zone" => Some(ScopeKind::Zone),
+            "tenant" => Some(ScopeKind::Tenant),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MaintenanceScope {
+    pub kind: ScopeKind,
+    pub value: String,
+}
+
+/// maintenance_windows.action
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum MaintenanceAction {
+    /// Stored without raising an alert
+    Suppress,
+    /// Stored and alerted at downgrade_to
+    Downgrade,
+}
+
+impl MaintenanceAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MaintenanceAction::Suppress => "suppress",
+            MaintenanceAction::Downgrade => "downgrade",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "suppress" => Some(MaintenanceAction::Suppress),
+            "downgrade" => Some(MaintenanceAction::Downgrade),
+            _ => None,
+        }
+    }
+}
+
+/// A detection class a window applies to; every field present must equal the detection's.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct DetectionClass {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detection_engine: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detection_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detection_category: Option<String>,
+}
+
+impl DetectionClass {
+    pub fn matches(&self, detection: &DetectionRecord) -> bool {
+        let exact = |want: &Option<String>, have: Option<&str>| want.as_deref().is_none_or(|w| Some(w) == have);
+        exact(&self.detection_engine, Some(&detection.detection_engine))
+            && exact(&self.detection_name, Some(&detection.detection_name))
+            && exact(&self.detection_category, detection.detection_category.as_deref())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let fields = [
+            ("detection_engine", &self.detection_engine),
+            ("detection_name", &self.detection_name),
+            ("detection_category", &self.detection_category),
+        ];
+        if fields.iter().all(|(_, v)| v.is_none()) {
+            return Err("a class needs detection_engine, detection_name or detection_category".to_string());
+        }
+        for (field, value) in fields {
+            if let Some(v) = value {
+                check_text(field, v)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A window as opened through the admin API.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MaintenanceWindowSpec {
+    pub scope: MaintenanceScope,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub action: MaintenanceAction,
+    /// severity_level label detections are downgraded to (downgrade only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub downgrade_to: Option<String>,
+    /// Detection classes the window applies to
+    pub classes: Vec<DetectionClass>,
+    /// Why the activity is expected (change reference)
+    pub reason: String,
+    pub created_by: String,
+}
+
+impl MaintenanceWindowSpec {
+    /// Check the window can be opened at `now`.
+    pub fn validate(&self, now: DateTime<Utc>) -> Result<(), String> {
+        check_text("scope.value", &self.scope.value)?;
+        check_text("created_by", &self.created_by)?;
+        if self.reason.trim().is_empty() {
+            return Err("reason is empty".to_string());
+        }
+        if self.ends_at <= self.starts_at {
+            return Err("ends_at must be after starts_at".to_string());
+        }
+        if self.ends_at <= now {
+            return Err(format!("ends_at {} is not in the future", self.ends_at.to_rfc3339()));
+        }
+        if self.ends_at - self.starts_at > Duration::hours(MAX_WINDOW_HOURS) {
+            return Err(format!("window is longer than {} hours", MAX_WINDOW_HOURS));
+        }
+        if self.starts_at > now + Duration::days(MAX_LEAD_DAYS) {
+            return Err(format!("starts_at is more than {} days ahead", MAX_LEAD_DAYS));
+        }
+        match (self.action, self.downgrade_to.as_deref()) {
+            (MaintenanceAction::Downgrade, None) => return Err("downgrade needs downgrade_to".to_string()),
+            (MaintenanceAction::Downgrade, Some(to)) if severity_rank(to).is_none() => {
+                return Err(format!("downgrade_to '{}' is not one of {}", to, SEVERITY_LEVELS.join("|")));
+            }
+            (MaintenanceAction::Suppress, Some(_)) => return Err("downgrade_to only applies to downgrade".to_string()),
+            _ => {}
+        }
+        if self.classes.is_empty() || self.classes.len() > MAX_CLASSES {
+            return Err(format!("classes needs 1 to {} entries", MAX_CLASSES));
+        }
+        self.classes.iter().try_for_each(DetectionClass::validate)
+    }
+
+    /// The window has an effect on `detection`, scope aside: a class matches and, for a
+    /// downgrade, the detection is more severe than downgrade_to.
+    pub fn matches(&self, detection: &DetectionRecord) -> bool {
+        if !self.classes.iter().any(|c| c.matches(detection)) {
+            return false;
+        }
+        match self.action {
+            MaintenanceAction::Suppress => true,
+            MaintenanceAction::Downgrade => {
+                match (self.downgrade_to.as_deref().and_then(severity_rank), severity_rank(&detection.severity)) {
+                    (Some(to), Some(rank)) => to < rank,
+                    _ => false,
+                }
+            }
+        }
+    }
+}
+
+/// Hosts, zones and tenant a detection is about, as far as window scopes go.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DetectionScope {
+    pub hosts: BTreeSet<String>,
+    pub zones: BTreeSet<String>,
+    pub tenant: Option<String>,
+}
+
+impl DetectionScope {
+    /// Hosts and zones named in a detection's artifacts.
+    pub fn from_artifacts(artifacts: &JsonValue) -> Self {
+        let text = |key: &str| artifacts.get(key).and_then(JsonValue::as_str).map(str::to_string);
+        Self {
+            hosts: text("component_id").into_iter().collect(),
+            zones: ["zone", "source_zone", "destination_zone"].iter().filter_map(|k| text(k)).collect(),
+            tenant: None,
+        }
+    }
+
+    pub fn covers(&self, scope: &MaintenanceScope) -> bool {
+        match scope.kind {
+            ScopeKind::Host => self.hosts.contains(&scope.value),
+            ScopeKind::Zone => self.zones.contains(&scope.value),
+            ScopeKind::Tenant => self.tenant.as_deref() == Some(scope.value.as_str()),
+        }
+    }
+}
+
+/// Window that applies to `detection` in `scope`: suppressing windows first, then in the order
+/// given (pure).
+pub fn select<'a>(
+    windows: &'a [(Uuid, MaintenanceWindowSpec)],
+    detection: &DetectionRecord,
+    scope: &DetectionScope,
+) -> Option<&'a (Uuid, MaintenanceWindowSpec)> {
+    windows
+        .iter()
+        .filter(|(_, w)| w.matches(detection) && scope.covers(&w.scope))
+        .min_by_key(|(_, w)| w.action != MaintenanceAction::Suppress)
+}
+
+/// What an active window did to a detection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaintenanceHit {
+    pub window_id: Uuid,
+    pub action: MaintenanceAction,
+    /// Severity the detection is stored and alerted at (downgrade only)
+    pub downgrade_to: Option<String>,
+}
+
+const SPEC_COLUMNS: &str = "window_id, scope_kind, scope_value, starts_at, ends_at, action, downgrade_to::text, classes, \
+                            reason, created_by";
+
+fn spec_from_row(r: &Row) -> Result<(Uuid, MaintenanceWindowSpec), String> {
+    let window_id: Uuid = r.get(0);
+    let kind: String = r.get(1);
+    let action: String = r.get(5);
+    let Json(classes): Json<Vec<DetectionClass>> =
+        r.try_get(7).map_err(|e| format!("maintenance window {} has unreadable classes: {}", window_id, e))?;
+    Ok((
+        window_id,
+        MaintenanceWindowSpec {
+            scope: MaintenanceScope {
+                kind: ScopeKind::parse(&kind).ok_or_else(|| format!("maintenance window {} has scope '{}'", window_id, kind))?,
+                value: r.get(2),
+            },
+            starts_at: r.get(3),
+            ends_at: r.get(4),
+            action: MaintenanceAction::parse(&action)
+                .ok_or_else(|| format!("maintenance window {} has action '{}'", window_id, action))?,
+            downgrade_to: r.get(6),
+            classes,
+            reason: r.get(8),
+            created_by: r.get(9),
+        },
+    ))
+}
+
+/// Hosts, zones and tenant of a detection: its artifacts, plus the agent they name.
+async fn resolve_scope(db: &Client, detection: &DetectionRecord) -> Result<DetectionScope, tokio_postgres::Error> {
+    let mut scope = DetectionScope::from_artifacts(&detection.artifacts);
+    let agent_id = detection.artifacts.get("agent_id").and_then(JsonValue::as_str).and_then(|s| Uuid::parse_str(s).ok());
+    let Some(agent_id) = agent_id else {
+        return Ok(scope);
+    };
+    let row = db
+        .query_opt(
+            r#"
+            SELECT a.host_hostname, a.host_fqdn, a.tenant_id,
+                   (SELECT r.zone FROM raw_events r
+                    WHERE r.source_agent_id = a.agent_id AND r.zone IS NOT NULL
+                    ORDER BY r.observed_at DESC LIMIT 1)
+            FROM agents a
+            WHERE a.agent_id = $1
+            "#,
+            &[&agent_id],
+        )
+        .await?;
+    if let Some(r) = row {
+        let hostname: Option<String> = r.get(0);
+        let fqdn: Option<String> = r.get(1);
+        let zone: Option<String> = r.get(3);
+        scope.hosts.extend(hostname.into_iter().chain(fqdn));
+        scope.zones.extend(zone);
+        scope.tenant = r.get(2);
+    }
+    Ok(scope)
+}
+
+/// Active window applying to `detection`, with the hit counted on it. Runs on the detection's
+/// transaction, so the count commits with the detection.
+pub async fn apply(db: &Client, detection: &DetectionRecord) -> Result<Option<MaintenanceHit>, tokio_postgres::Error> {
+    let rows = db
+        .query(
+            &format!(
+                r#"
+                SELECT {}
+                FROM maintenance_windows
+                WHERE cancelled_at IS NULL AND starts_at <= now() AND ends_at > now()
+                ORDER BY created_at, window_id
+                "#,
+                SPEC_COLUMNS
+            ),
+            &[],
+        )
+        .await?;
+    let mut windows = Vec::new();
+    for row in &rows {
+        match spec_from_row(row) {
+            Ok(w) if w.1.matches(detection) => windows.push(w),
+            Ok(_) => {}
+            // An unreadable window must not hide anything
+            Err(e) => error!("FAIL-CLOSED: {}; ignored", e),
+        }
+    }
+    // The agent lookup is only paid for while a window could apply
+    if windows.is_empty() {
+        return Ok(None);
+    }
+    let scope = resolve_scope(db, detection).await?;
+    let Some((window_id, window)) = select(&windows, detection, &scope) else {
+        return Ok(None);
+    };
+    let counter = match window.action {
+        MaintenanceAction::Suppress => "suppressed_count",
+        MaintenanceAction::Downgrade => "downgraded_count",
+    };
+    db.execute(
+        &format!(
+            "UPDATE maintenance_windows SET {0} = {0} + 1, last_hit_at = now() WHERE window_id = $1",
+            counter
+        ),
+        &[window_id],
+    )
+    .await?;
+    Ok(Some(MaintenanceHit { window_id: *window_id, action: window.action, downgrade_to: window.downgrade_to.clone() }))
+}
+
+/// One window as listed by the admin API.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MaintenanceWindow {
+    pub window_id: Uuid,
+    #[serde(flatten)]
+    pub spec: MaintenanceWindowSpec,
+    pub created_at: DateTime<Utc>,
+    pub cancelled_at: Option<DateTime<Utc>>,
+    pub cancelled_by: Option<String>,
+    pub cancel_reason: Option<String>,
+    pub suppressed_count: i64,
+    pub downgraded_count: i64,
+    pub last_hit_at: Option<DateTime<Utc>>,
+    /// scheduled, active or ended (past ends_at or cancelled)
+    pub status: String,
+}
+
+impl MaintenanceWindow {
+    fn status_at(spec: &MaintenanceWindowSpec, cancelled_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> &'static str {
+        if cancelled_at.is_some() || spec.ends_at <= now {
+            "ended"
+        } else if spec.starts_at > now {
+            "scheduled"
+        } else {
+            "active"
+        }
+    }
+}
+
+const WINDOW_COLUMNS: &str = "created_at, cancelled_at, cancelled_by, cancel_reason, suppressed_count, downgraded_count, \
+                              last_hit_at";
+
+fn window_from_row(r: &Row, now: DateTime<Utc>) -> Result<MaintenanceWindow, String> {
+    let (window_id, spec) = spec_from_row(r)?;
+    let cancelled_at: Option<DateTime<Utc>> = r.get(11);
+    Ok(MaintenanceWindow {
+        window_id,
+        status: MaintenanceWindow::status_at(&spec, cancelled_at, now).to_string(),
+        spec,
+        created_at: r.get(10),
+        cancelled_at,
+        cancelled_by: r.get(12),
+        cancel_reason: r.get(13),
+        suppressed_count: r.get(14),
+        downgraded_count: r.get(15),
+        last_hit_at: r.get(16),
+    })
+}
+
+/// All windows, scheduled, active and ended.
+pub async fn list(db: &Client, now: DateTime<Utc>) -> Result<Vec<MaintenanceWindow>, String> {
+    let rows = db
+        .query(&format!("SELECT {}, {} FROM maintenance_windows", SPEC_COLUMNS, WINDOW_COLUMNS), &[])
+        .await
+        .map_err(|e| format!("Failed to read maintenance windows: {}", e))?;
+    rows.iter().map(|r| window_from_row(r, now)).collect()
+}
+
+/// Detections of one class a window held back.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct HeldBackClass {
+    pub detection_engine: String,
+    pub detection_name: String,
+    pub action: MaintenanceAction,
+    /// Severity the detections were raised at
+    pub original_severity: String,
+    pub count: i64,
+    pub first_at: DateTime<Utc>,
+    pub last_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct HeldBackDetection {
+    pub detection_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub detection_engine: String,
+    pub detection_name: String,
+    pub action: MaintenanceAction,
+    /// Severity stored (downgrade_to for downgraded detections)
+    pub severity: String,
+    pub original_severity: String,
+    #[schema(value_type = Object)]
+    pub artifacts: Option<JsonValue>,
+}
+
+/// What a window suppressed and downgraded.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MaintenanceReport {
+    pub window: MaintenanceWindow,
+    pub suppressed: i64,
+    pub downgraded: i64,
+    /// By engine, name, action and original severity
+    pub classes: Vec<HeldBackClass>,
+    /// Newest first, at most MAX_REPORTED_DETECTIONS
+    pub detections: Vec<HeldBackDetection>,
+}
+
+/// Report of one window (None: unknown window).
+pub async fn report(db: &Client, window_id: Uuid, now: DateTime<Utc>) -> Result<Option<MaintenanceReport>, String> {
+    let read_err = |e: tokio_postgres::Error| format!("Failed to read maintenance window {}: {}", window_id, e);
+    let row = db
+        .query_opt(
+            &format!("SELECT {}, {} FROM maintenance_windows WHERE window_id = $1", SPEC_COLUMNS, WINDOW_COLUMNS),
+            &[&window_id],
+        )
+        .await
+        .map_err(read_err)?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    let window = window_from_row(&row, now)?;
+    // Downgraded detections keep their original severity; suppressed ones keep their own
+    let action_of = |original: &Option<String>| match original {
+        Some(_) => MaintenanceAction::Downgrade,
+        None => MaintenanceAction::Suppress,
+    };
+    let classes: Vec<HeldBackClass> = db
+        .query(
+            r#"
+            SELECT detection_engine, detection_name, original_severity::text,
+                   COALESCE(original_severity, severity)::text, count(*), min(created_at), max(created_at)
+            FROM detection_results
+            WHERE maintenance_window_id = $1
+            GROUP BY 1, 2, 3, 4
+            ORDER BY 5 DESC, 1, 2, 4
+            "#,
+            &[&window_id],
+        )
+        .await
+        .map_err(read_err)?
+        .iter()
+        .map(|r| HeldBackClass {
+            detection_engine: r.get(0),
+            detection_name: r.get(1),
+            action: action_of(&r.get(2)),
+            original_severity: r.get(3),
+            count: r.get(4),
+            first_at: r.get(5),
+            last_at: r.get(6),
+        })
+        .collect();
+    let detections = db
+        .query(
+            r#"
+            SELECT detection_id, created_at, detection_engine, detection_name, severity::text,
+                   original_severity::text, artifacts
+            FROM detection_results
+            WHERE maintenance_window_id = $1
+            ORDER BY created_at DESC, detection_id
+            LIMIT $2
+            "#,
+            &[&window_id, &MAX_REPORTED_DETECTIONS],
+        )
+        .await
+        .map_err(read_err)?
+        .iter()
+        .map(|r| {
+            let severity: String = r.get(4);
+            let original: Option<String> = r.get(5);
+            HeldBackDetection {
+                detection_id: r.get(0),
+                created_at: r.get(1),
+                detection_engine: r.get(2),
+                detection_name: r.get(3),
+                action: action_of(&original),
+                original_severity: original.unwrap_or_else(|| severity.clone()),
+                severity,
+                artifacts: r.get(6),
+            }
+        })
+        .collect();
+    let total = |action| classes.iter().filter(|c| c.action == action).map(|c| c.count).sum();
+    Ok(Some(MaintenanceReport {
+        suppressed: total(MaintenanceAction::Suppress),
+        downgraded: total(MaintenanceAction::Downgrade),
+        window,
+        classes,
+        detections,
+    }))
+}
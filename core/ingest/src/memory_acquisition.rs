@@ -0,0 +1,352 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/memory_acquisition.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Operator-triggered memory acquisition - policy gate for acquisition requests, chunk spool with per-chunk and whole-image SHA-256 verification, chain-of-custody manifest for the evidence store, and expiry of stalled acquisitions
+
+/*
+ * Memory Acquisition
+ *
+ * Memory images are never taken automatically. An operator asks for one over the admin API
+ * (POST /admin/memory-acquisitions) naming the Linux agent, the scope and a reason; the request
+ * passes the ingest policy before a memory_acquisitions row is queued (status requested):
+ *
+ *   enabled        RANSOMEYE_INGEST_MEMORY_ACQUISITION (default off)
+ *   scopes         RANSOMEYE_INGEST_MEMORY_SCOPES: process (one pid) and/or full (whole host)
+ *   two persons    approved_by must name someone other than requested_by
+ *   bounds         RANSOMEYE_INGEST_MEMORY_MAX_BYTES per image, _CHUNK_BYTES per upload
+ *
+ * The agent must have acquisition enabled itself (AGENT_MEMORY_ACQUISITION) and picks up its own
+ * requests over the control channel (agent bearer token, linux_agent only):
+ * POST /agents/memory-acquisitions/claim, then the image in order, one chunk per
+ * POST /agents/memory-acquisitions/chunk with the chunk's SHA-256, then
+ * POST /agents/memory-acquisitions/complete with the chunk count, size and SHA-256 of the whole
+ * image (or /fail). Every chunk is checked against its hash on arrival, spooled as
+ * <spool>/<acquisition_id>/<index>.chunk and recorded in memory_acquisition_chunks; completion
+ * re-reads the spooled chunks, checks them and the image hash, and writes the manifest
+ * <spool>/<acquisition_id>.json with the chain of custody (who requested and approved, which
+ * agent acquired with which tool, when ingest verified it). `reporting import-memory` seals it.
+ *
+ * Requests not claimed within CLAIM_WINDOW_SECS, and acquisitions without a chunk for
+ * IDLE_TIMEOUT_SECS, expire and their spooled chunks are removed. One acquisition per agent is
+ * pending or running at a time. Every step is audited.
+ */
+
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use crypto::digest::Sha256;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+use tracing::warn;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// A request no agent claimed within this window expires
+pub const CLAIM_WINDOW_SECS: i64 = 3600;
+/// An acquisition without progress (claim or chunk) for this long expires
+pub const IDLE_TIMEOUT_SECS: i64 = 1800;
+/// Longest reason, tool name or failure text accepted
+pub const MAX_TEXT_BYTES: usize = 1024;
+
+const DEFAULT_MAX_BYTES: u64 = 16 * 1024 * 1024 * 1024;
+const MAX_MAX_BYTES: u64 = 1024 * 1024 * 1024 * 1024;
+const DEFAULT_CHUNK_BYTES: u64 = 8 * 1024 * 1024;
+const MIN_CHUNK_BYTES: u64 = 64 * 1024;
+const MAX_CHUNK_BYTES: u64 = 64 * 1024 * 1024;
+const DEFAULT_SPOOL_DIR: &str = "/var/lib/ransomeye/ingest/memory-spool";
+
+fn env_bounded(key: &str, default_value: u64, min: u64, max: u64) -> Result<u64, String> {
+    match std::env::var(key) {
+        Ok(v) => v
+            .parse::<u64>()
+            .ok()
+            .filter(|n| (min..=max).contains(n))
+            .ok_or_else(|| format!("Invalid {} '{}' (expected {}..={})", key, v, min, max)),
+        Err(_) => Ok(default_value),
+    }
+}
+
+/// What to image: one process or all physical memory of the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AcquisitionScope {
+    Process,
+    Full,
+}
+
+impl AcquisitionScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AcquisitionScope::Process => "process",
+            AcquisitionScope::Full => "full",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "process" => Some(AcquisitionScope::Process),
+            "full" => Some(AcquisitionScope::Full),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryAcquisitionPolicy {
+    /// Scopes operators may request
+    pub scopes: Vec<AcquisitionScope>,
+    /// Largest image accepted
+    pub max_bytes: u64,
+    /// Size of each uploaded chunk (the last one may be shorter)
+    pub chunk_bytes: u64,
+    /// Spooled chunks and manifests, until `reporting import-memory` takes them
+    pub spool_dir: PathBuf,
+}
+
+impl MemoryAcquisitionPolicy {
+    /// RANSOMEYE_INGEST_MEMORY_ACQUISITION=true|1 allows memory acquisition requests (default off);
+    /// RANSOMEYE_INGEST_MEMORY_SCOPES (comma-separated process,full; default process),
+    /// RANSOMEYE_INGEST_MEMORY_MAX_BYTES (default 16 GiB, at most 1 TiB),
+    /// RANSOMEYE_INGEST_MEMORY_CHUNK_BYTES (default 8 MiB, 64 KiB..=64 MiB),
+    /// RANSOMEYE_INGEST_MEMORY_SPOOL_DIR (default /var/lib/ransomeye/ingest/memory-spool).
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let enabled = std::env::var("RANSOMEYE_INGEST_MEMORY_ACQUISITION")
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+        if !enabled {
+            return Ok(None);
+        }
+        let scopes_raw = std::env::var("RANSOMEYE_INGEST_MEMORY_SCOPES").unwrap_or_else(|_| "process".to_string());
+        let mut scopes = Vec::new();
+        for s in scopes_raw.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let scope = AcquisitionScope::parse(s)
+                .ok_or_else(|| format!("Invalid RANSOMEYE_INGEST_MEMORY_SCOPES entry '{}' (expected process or full)", s))?;
+            if !scopes.contains(&scope) {
+                scopes.push(scope);
+            }
+        }
+        if scopes.is_empty() {
+            return Err("RANSOMEYE_INGEST_MEMORY_SCOPES must name at least one scope".to_string());
+        }
+        let chunk_bytes = env_bounded("RANSOMEYE_INGEST_MEMORY_CHUNK_BYTES", DEFAULT_CHUNK_BYTES, MIN_CHUNK_BYTES, MAX_CHUNK_BYTES)?;
+        Ok(Some(Self {
+            scopes,
+            max_bytes: env_bounded("RANSOMEYE_INGEST_MEMORY_MAX_BYTES", DEFAULT_MAX_BYTES, chunk_bytes, MAX_MAX_BYTES)?,
+            chunk_bytes,
+            spool_dir: PathBuf::from(
+                std::env::var("RANSOMEYE_INGEST_MEMORY_SPOOL_DIR").unwrap_or_else(|_| DEFAULT_SPOOL_DIR.to_string()),
+            ),
+        }))
+    }
+
+    /// Policy gate for an operator request; the error is the reason it is refused.
+    pub fn check(&self, req: &AcquisitionRequest) -> Result<(), String> {
+        if !self.scopes.contains(&req.scope) {
+            return Err(format!("scope '{}' is not allowed by policy", req.scope.as_str()));
+        }
+        match (req.scope, req.pid) {
+            (AcquisitionScope::Process, None) => return Err("a process acquisition needs a pid".to_string()),
+            (AcquisitionScope::Process, Some(pid)) if pid < 1 => return Err(format!("invalid pid {}", pid)),
+            (AcquisitionScope::Full, Some(_)) => return Err("a full acquisition takes no pid".to_string()),
+            _ => {}
+        }
+        for (field, value) in [("reason", &req.reason), ("requested_by", &req.requested_by), ("approved_by", &req.approved_by)] {
+            if value.trim().is_empty() || value.len() > MAX_TEXT_BYTES {
+                return Err(format!("{} must be 1..={} bytes", field, MAX_TEXT_BYTES));
+            }
+        }
+        if req.requested_by.trim().eq_ignore_ascii_case(req.approved_by.trim()) {
+            return Err("approved_by must be a different person than requested_by".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Operator request (POST /admin/memory-acquisitions).
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AcquisitionRequest {
+    pub agent_id: Uuid,
+    pub scope: AcquisitionScope,
+    /// Process to image (process scope only)
+    pub pid: Option<i32>,
+    /// Incident the image is taken for (optional)
+    pub incident_id: Option<Uuid>,
+    /// Case or incident reference justifying the acquisition
+    pub reason: String,
+    pub requested_by: String,
+    /// Second person authorizing the acquisition (must differ from requested_by)
+    pub approved_by: String,
+}
+
+/// Acquisition handed to the agent (POST /agents/memory-acquisitions/claim).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct AcquisitionOrder {
+    pub acquisition_id: Uuid,
+    pub scope: AcquisitionScope,
+    pub pid: Option<i32>,
+    /// Ingest refuses images larger than this
+    pub max_bytes: i64,
+    /// Upload chunk size (the last chunk may be shorter)
+    pub chunk_bytes: i64,
+}
+
+/// memory_acquisitions.status
+pub const ACQUISITION_STATUSES: &[&str] = &["requested", "acquiring", "acquired", "failed", "expired"];
+
+/// An acquisition request and its outcome, as listed by the admin API.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MemoryAcquisition {
+    pub acquisition_id: Uuid,
+    pub agent_id: Uuid,
+    pub incident_id: Option<Uuid>,
+    /// process or full
+    pub scope: String,
+    pub pid: Option<i32>,
+    pub reason: String,
+    pub requested_by: String,
+    pub approved_by: String,
+    /// requested, acquiring, acquired, failed or expired
+    pub status: String,
+    pub requested_at: DateTime<Utc>,
+    pub claimed_at: Option<DateTime<Utc>>,
+    pub last_activity_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub chunks_received: i32,
+    pub bytes_received: i64,
+    pub image_sha256: Option<String>,
+    pub tool: Option<String>,
+    pub failure_reason: Option<String>,
+}
+
+/// One spooled chunk as recorded on arrival.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkEntry {
+    pub index: u32,
+    pub sha256: String,
+    pub bytes: u64,
+}
+
+/// One step in the chain of custody.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustodyEntry {
+    pub at: DateTime<Utc>,
+    /// requested, approved, claimed, acquired, verified
+    pub action: String,
+    /// Person, agent (component identity) or service that performed the step
+    pub actor: String,
+    pub detail: String,
+}
+
+/// Sidecar of a spooled image (<acquisition_id>.json next to the <acquisition_id>/ chunk
+/// directory); read by the reporting importer.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpoolManifest {
+    pub acquisition_id: Uuid,
+    pub agent_id: Uuid,
+    pub agent_component_id: String,
+    pub incident_id: Option<Uuid>,
+    pub scope: AcquisitionScope,
+    pub pid: Option<i32>,
+    pub reason: String,
+    pub requested_by: String,
+    pub approved_by: String,
+    pub requested_at: DateTime<Utc>,
+    pub claimed_at: DateTime<Utc>,
+    pub completed_at: DateTime<Utc>,
+    pub tool: String,
+    pub tool_version: Option<String>,
+    pub image_sha256: String,
+    pub image_bytes: u64,
+    pub chunk_dir: String,
+    pub chunks: Vec<ChunkEntry>,
+    pub custody: Vec<CustodyEntry>,
+}
+
+fn write_atomic(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    let mut file = fs::File::create(&tmp)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)
+}
+
+/// Spool directory of one acquisition's chunks.
+pub fn chunk_dir(spool_dir: &Path, acquisition_id: Uuid) -> PathBuf {
+    spool_dir.join(acquisition_id.to_string())
+}
+
+/// File name of a spooled chunk (zero-padded so a directory listing is in image order).
+pub fn chunk_file_name(index: u32) -> String {
+    format!("{:08}.chunk", index)
+}
+
+/// Spool one chunk. A retried upload of the same chunk replaces it.
+pub fn write_chunk(spool_dir: &Path, acquisition_id: Uuid, index: u32, data: &[u8]) -> std::io::Result<()> {
+    let dir = chunk_dir(spool_dir, acquisition_id);
+    fs::create_dir_all(&dir)?;
+    write_atomic(&dir.join(chunk_file_name(index)), data)
+}
+
+/// Re-read the spooled chunks in order, check each against its recorded SHA-256 and size, and
+/// return the SHA-256 and size of the whole image.
+pub fn verify_chunks(spool_dir: &Path, acquisition_id: Uuid, chunks: &[ChunkEntry]) -> Result<(String, u64), String> {
+    let dir = chunk_dir(spool_dir, acquisition_id);
+    let mut image = Sha256::new();
+    let mut total = 0u64;
+    let mut buf = Vec::new();
+    for (expected_index, chunk) in chunks.iter().enumerate() {
+        if chunk.index as usize != expected_index {
+            return Err(format!("chunk {} missing (found {})", expected_index, chunk.index));
+        }
+        buf.clear();
+        fs::File::open(dir.join(chunk_file_name(chunk.index)))
+            .and_then(|mut f| f.read_to_end(&mut buf))
+            .map_err(|e| format!("chunk {} not readable: {}", chunk.index, e))?;
+        if buf.len() as u64 != chunk.bytes {
+            return Err(format!("chunk {} is {} bytes, {} were received", chunk.index, buf.len(), chunk.bytes));
+        }
+        let sha256 = hex::encode(Sha256::digest(&buf));
+        if sha256 != chunk.sha256 {
+            return Err(format!("chunk {} SHA-256 {} does not match {} received", chunk.index, sha256, chunk.sha256));
+        }
+        image.update(&buf);
+        total += buf.len() as u64;
+    }
+    Ok((hex::encode(image.finalize()), total))
+}
+
+/// Write the manifest last: the importer only takes acquisitions whose manifest exists.
+pub fn write_manifest(spool_dir: &Path, manifest: &SpoolManifest) -> std::io::Result<()> {
+    let json = serde_json::to_vec_pretty(manifest).map_err(std::io::Error::other)?;
+    write_atomic(&spool_dir.join(format!("{}.json", manifest.acquisition_id)), &json)
+}
+
+/// Remove everything spooled for an acquisition that will not be completed.
+pub fn remove_spool(spool_dir: &Path, acquisition_id: Uuid) {
+    let _ = fs::remove_file(spool_dir.join(format!("{}.json", acquisition_id)));
+    let _ = fs::remove_dir_all(chunk_dir(spool_dir, acquisition_id));
+}
+
+/// Expire requests nobody claimed in time and stalled acquisitions, and drop their chunks;
+/// returns the acquisitions expired.
+pub async fn expire_stale(db: &Client, spool_dir: &Path) -> Result<Vec<Uuid>, tokio_postgres::Error> {
+    let rows = db
+        .query(
+            r#"
+            UPDATE memory_acquisitions
+            SET status = 'expired', finished_at = now()
+            WHERE (status = 'requested' AND requested_at < now() - make_interval(secs => $1))
+               OR (status = 'acquiring' AND last_activity_at < now() - make_interval(secs => $2))
+            RETURNING acquisition_id
+            "#,
+            &[&(CLAIM_WINDOW_SECS as f64), &(IDLE_TIMEOUT_SECS as f64)],
+        )
+        .await?;
+    let expired: Vec<Uuid> = rows.iter().map(|r| r.get(0)).collect();
+    for id in &expired {
+        warn!("Memory acquisition {} expired", id);
+        remove_spool(spool_dir, *id);
+    }
+    Ok(expired)
+}
@@ -0,0 +1,444 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/oidc.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: OpenID Connect relying party for operator sign-in - provider discovery, JWKS cache with refresh on unknown key ids, RS256 ID/access token validation and the authorization code exchange with PKCE
+
+/*
+ * OIDC Provider
+ *
+ * The issuer's discovery document and signing keys (JWKS) are fetched on first use and cached;
+ * a token signed with a key id not in the cache triggers one refetch (at most once a minute), so
+ * IdP key rotation needs no restart. Startup does not depend on the IdP being reachable.
+ *
+ * Only RS256 is accepted; "none" and HMAC algorithms are refused whatever the token header says.
+ * iss must equal the configured issuer, aud must contain the expected audience (the API audience
+ * for bearer tokens, the client id for ID tokens), exp and nbf are checked with 60s of clock
+ * leeway, and an ID token must carry the nonce of its login.
+ */
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::{DateTime, TimeZone, Utc};
+use crypto::digest::Sha256;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use thiserror::Error;
+use tokio::sync::Mutex as AsyncMutex;
+use tracing::{info, warn};
+use url::Url;
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+/// Unknown key ids refetch the JWKS at most this often
+const MIN_JWKS_REFRESH: Duration = Duration::from_secs(60);
+pub const CLOCK_LEEWAY_SECS: i64 = 60;
+pub const DEFAULT_SCOPES: &str = "openid profile email";
+pub const DEFAULT_GROUPS_CLAIM: &str = "groups";
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum OidcError {
+    #[error("Malformed token: {0}")]
+    Malformed(String),
+    #[error("Unsupported token algorithm '{0}' (RS256 required)")]
+    UnsupportedAlgorithm(String),
+    #[error("No signing key '{0}' in the provider JWKS")]
+    UnknownKey(String),
+    #[error("Token signature verification failed")]
+    BadSignature,
+    #[error("Token claim rejected: {0}")]
+    InvalidClaim(String),
+    #[error("Token expired at {0}")]
+    Expired(DateTime<Utc>),
+    #[error("Token not valid before {0}")]
+    NotYetValid(DateTime<Utc>),
+    #[error("Identity provider unavailable: {0}")]
+    Provider(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    /// Issuer URL exactly as it appears in the tokens' iss claim
+    pub issuer: String,
+    pub client_id: String,
+    /// Needed for the authorization code exchange (the dashboard login)
+    pub client_secret: Option<String>,
+    /// This service's /auth/oidc/callback as registered with the IdP (None: no dashboard login)
+    pub redirect_url: Option<Url>,
+    /// aud expected in bearer tokens presented to the API
+    pub audience: String,
+    /// Claim holding the user's groups; a dotted path reaches into nested objects
+    pub groups_claim: String,
+    pub scopes: String,
+}
+
+/// Subset of the discovery document the relying party uses.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Jwks {
+    pub keys: Vec<Jwk>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Jwk {
+    pub kty: String,
+    pub kid: Option<String>,
+    pub alg: Option<String>,
+    #[serde(rename = "use")]
+    pub key_use: Option<String>,
+    pub n: Option<String>,
+    pub e: Option<String>,
+}
+
+struct RsaComponents {
+    n: Vec<u8>,
+    e: Vec<u8>,
+}
+
+/// RS256 signing keys by kid ("" for a key published without one).
+struct KeySet {
+    keys: HashMap<String, RsaComponents>,
+    fetched_at: Option<Instant>,
+}
+
+impl KeySet {
+    fn from_jwks(jwks: &Jwks) -> Self {
+        let mut keys = HashMap::new();
+        for jwk in &jwks.keys {
+            let usable = jwk.kty == "RSA"
+                && jwk.key_use.as_deref().is_none_or(|u| u == "sig")
+                && jwk.alg.as_deref().is_none_or(|a| a == "RS256");
+            if !usable {
+                continue;
+            }
+            let (Some(n), Some(e)) = (&jwk.n, &jwk.e) else { continue };
+            match (URL_SAFE_NO_PAD.decode(n), URL_SAFE_NO_PAD.decode(e)) {
+                (Ok(n), Ok(e)) => {
+                    keys.insert(jwk.kid.clone().unwrap_or_default(), RsaComponents { n, e });
+                }
+                _ => warn!("Skipping JWKS key {:?}: invalid base64url modulus or exponent", jwk.kid),
+            }
+        }
+        Self { keys, fetched_at: Some(Instant::now()) }
+    }
+
+    /// A token without a kid is accepted only when the provider publishes a single key.
+    fn get(&self, kid: Option<&str>) -> Option<&RsaComponents> {
+        match kid {
+            Some(kid) => self.keys.get(kid),
+            None if self.keys.len() == 1 => self.keys.values().next(),
+            None => None,
+        }
+    }
+}
+
+/// Claims of a validated token the operator API uses.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifiedToken {
+    pub subject: String,
+    /// Preferred display name (preferred_username, email, else the subject)
+    pub name: String,
+    pub groups: Vec<String>,
+    pub expires_at: DateTime<Utc>,
+    pub claims: JsonValue,
+}
+
+#[derive(Deserialize)]
+struct JwtHeader {
+    alg: String,
+    kid: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: Option<String>,
+}
+
+pub struct OidcProvider {
+    config: OidcConfig,
+    http: reqwest::Client,
+    metadata: RwLock<Option<ProviderMetadata>>,
+    keys: RwLock<KeySet>,
+    /// One discovery or JWKS fetch at a time
+    fetching: AsyncMutex<()>,
+}
+
+impl OidcProvider {
+    pub fn new(config: OidcConfig) -> Result<Self, String> {
+        let http = reqwest::Client::builder()
+            .timeout(HTTP_TIMEOUT)
+            .build()
+            .map_err(|e| format!("cannot build OIDC HTTP client: {}", e))?;
+        Ok(Self {
+            config,
+            http,
+            metadata: RwLock::new(None),
+            keys: RwLock::new(KeySet { keys: HashMap::new(), fetched_at: None }),
+            fetching: AsyncMutex::new(()),
+        })
+    }
+
+    /// Provider with discovery and keys already known (tests, air-gapped IdPs); unknown key ids
+    /// still refetch `metadata.jwks_uri` once the refresh interval has passed.
+    pub fn with_keys(config: OidcConfig, metadata: ProviderMetadata, jwks: &Jwks) -> Result<Self, String> {
+        let provider = Self::new(config)?;
+        *provider.metadata.write().unwrap_or_else(|e| e.into_inner()) = Some(metadata);
+        *provider.keys.write().unwrap_or_else(|e| e.into_inner()) = KeySet::from_jwks(jwks);
+        Ok(provider)
+    }
+
+    pub fn config(&self) -> &OidcConfig {
+        &self.config
+    }
+
+    /// Discovery document, fetched once.
+    pub async fn metadata(&self) -> Result<ProviderMetadata, OidcError> {
+        if let Some(metadata) = self.metadata.read().unwrap_or_else(|e| e.into_inner()).clone() {
+            return Ok(metadata);
+        }
+        let _fetching = self.fetching.lock().await;
+        if let Some(metadata) = self.metadata.read().unwrap_or_else(|e| e.into_inner()).clone() {
+            return Ok(metadata);
+        }
+        let url = format!("{}/.well-known/openid-configuration", self.config.issuer.trim_end_matches('/'));
+        let metadata: ProviderMetadata = self.get_json(&url).await?;
+        if metadata.issuer.trim_end_matches('/') != self.config.issuer.trim_end_matches('/') {
+            return Err(OidcError::Provider(format!(
+                "discovery issuer '{}' does not match configured issuer '{}'",
+                metadata.issuer, self.config.issuer
+            )));
+        }
+        info!("OIDC discovery loaded for {} (jwks_uri={})", metadata.issuer, metadata.jwks_uri);
+        *self.metadata.write().unwrap_or_else(|e| e.into_inner()) = Some(metadata.clone());
+        Ok(metadata)
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, url: &str) -> Result<T, OidcError> {
+        let response = self
+            .http
+            .get(url)
+            .send()
+            .await
+            .map_err(|e| OidcError::Provider(format!("GET {}: {}", url, e)))?;
+        if !response.status().is_success() {
+            return Err(OidcError::Provider(format!("GET {}: HTTP {}", url, response.status())));
+        }
+        response
+            .json()
+            .await
+            .map_err(|e| OidcError::Provider(format!("GET {}: invalid JSON: {}", url, e)))
+    }
+
+    /// Refetch the JWKS unless that happened within the last minute.
+    async fn refresh_keys(&self) -> Result<(), OidcError> {
+        let metadata = self.metadata().await?;
+        let _fetching = self.fetching.lock().await;
+        let recent = self
+            .keys
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .fetched_at
+            .is_some_and(|at| at.elapsed() < MIN_JWKS_REFRESH);
+        if recent {
+            return Ok(());
+        }
+        let jwks: Jwks = self.get_json(&metadata.jwks_uri).await?;
+        let keys = KeySet::from_jwks(&jwks);
+        info!("OIDC JWKS refreshed: {} RS256 key(s)", keys.keys.len());
+        *self.keys.write().unwrap_or_else(|e| e.into_inner()) = keys;
+        Ok(())
+    }
+
+    /// Bearer token presented to the operator API (aud = the configured API audience).
+    pub async fn validate_access_token(&self, token: &str, now: DateTime<Utc>) -> Result<VerifiedToken, OidcError> {
+        self.validate(token, &self.config.audience, None, now).await
+    }
+
+    /// ID token of a dashboard login (aud = client id, nonce of that login).
+    pub async fn validate_id_token(&self, token: &str, nonce: &str, now: DateTime<Utc>) -> Result<VerifiedToken, OidcError> {
+        self.validate(token, &self.config.client_id, Some(nonce), now).await
+    }
+
+    async fn validate(
+        &self,
+        token: &str,
+        audience: &str,
+        nonce: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Result<VerifiedToken, OidcError> {
+        let parts: Vec<&str> = token.split('.').collect();
+        if parts.len() != 3 {
+            return Err(OidcError::Malformed("expected header.payload.signature".to_string()));
+        }
+        let header: JwtHeader = decode_segment(parts[0], "header")?;
+        if header.alg != "RS256" {
+            return Err(OidcError::UnsupportedAlgorithm(header.alg));
+        }
+        let signature = URL_SAFE_NO_PAD
+            .decode(parts[2])
+            .map_err(|e| OidcError::Malformed(format!("signature encoding: {}", e)))?;
+        let signing_input = &token[..parts[0].len() + 1 + parts[1].len()];
+
+        let kid = header.kid.as_deref();
+        if !self.verify_signature(kid, signing_input.as_bytes(), &signature)? {
+            self.refresh_keys().await?;
+            if !self.verify_signature(kid, signing_input.as_bytes(), &signature)? {
+                return Err(OidcError::UnknownKey(kid.unwrap_or("").to_string()));
+            }
+        }
+
+        let claims: JsonValue = decode_segment(parts[1], "payload")?;
+        self.check_claims(claims, audience, nonce, now)
+    }
+
+    /// Ok(false): no key for this kid (caller may refresh the JWKS).
+    fn verify_signature(&self, kid: Option<&str>, signing_input: &[u8], signature: &[u8]) -> Result<bool, OidcError> {
+        let keys = self.keys.read().unwrap_or_else(|e| e.into_inner());
+        let Some(key) = keys.get(kid) else { return Ok(false) };
+        crypto::signature::verify_rsa_pkcs1_sha256(&key.n, &key.e, signing_input, signature)
+            .map(|_| true)
+            .map_err(|_| OidcError::BadSignature)
+    }
+
+    fn check_claims(
+        &self,
+        claims: JsonValue,
+        audience: &str,
+        nonce: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Result<VerifiedToken, OidcError> {
+        let iss = claims.get("iss").and_then(|v| v.as_str()).unwrap_or("");
+        if iss.trim_end_matches('/') != self.config.issuer.trim_end_matches('/') {
+            return Err(OidcError::InvalidClaim(format!("iss '{}'", iss)));
+        }
+        let aud_ok = match claims.get("aud") {
+            Some(JsonValue::String(aud)) => aud == audience,
+            Some(JsonValue::Array(auds)) => auds.iter().any(|a| a.as_str() == Some(audience)),
+            _ => false,
+        };
+        if !aud_ok {
+            return Err(OidcError::InvalidClaim(format!("aud does not contain '{}'", audience)));
+        }
+        let exp = timestamp_claim(&claims, "exp")?.ok_or_else(|| OidcError::InvalidClaim("missing exp".to_string()))?;
+        if now > exp + chrono::Duration::seconds(CLOCK_LEEWAY_SECS) {
+            return Err(OidcError::Expired(exp));
+        }
+        if let Some(nbf) = timestamp_claim(&claims, "nbf")? {
+            if now + chrono::Duration::seconds(CLOCK_LEEWAY_SECS) < nbf {
+                return Err(OidcError::NotYetValid(nbf));
+            }
+        }
+        if let Some(expected) = nonce {
+            let presented = claims.get("nonce").and_then(|v| v.as_str()).unwrap_or("");
+            if !crypto::constant_time_eq(presented.as_bytes(), expected.as_bytes()) {
+                return Err(OidcError::InvalidClaim("nonce mismatch".to_string()));
+            }
+        }
+        let subject = claims
+            .get("sub")
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.is_empty())
+            .ok_or_else(|| OidcError::InvalidClaim("missing sub".to_string()))?
+            .to_string();
+        let name = ["preferred_username", "email"]
+            .iter()
+            .find_map(|c| claims.get(*c).and_then(|v| v.as_str()))
+            .unwrap_or(&subject)
+            .to_string();
+        let groups = groups_of(&claims, &self.config.groups_claim);
+        Ok(VerifiedToken { subject, name, groups, expires_at: exp, claims })
+    }
+
+    /// Where the dashboard sends the browser to sign in.
+    pub async fn authorization_url(&self, state: &str, nonce: &str, code_challenge: &str) -> Result<Url, OidcError> {
+        let redirect = self
+            .config
+            .redirect_url
+            .as_ref()
+            .ok_or_else(|| OidcError::Provider("RANSOMEYE_OIDC_REDIRECT_URL not configured".to_string()))?;
+        let metadata = self.metadata().await?;
+        let mut url = Url::parse(&metadata.authorization_endpoint)
+            .map_err(|e| OidcError::Provider(format!("authorization_endpoint: {}", e)))?;
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.config.client_id)
+            .append_pair("redirect_uri", redirect.as_str())
+            .append_pair("scope", &self.config.scopes)
+            .append_pair("state", state)
+            .append_pair("nonce", nonce)
+            .append_pair("code_challenge", code_challenge)
+            .append_pair("code_challenge_method", "S256");
+        Ok(url)
+    }
+
+    /// Authorization code -> ID token (validated by the caller with the login's nonce).
+    pub async fn exchange_code(&self, code: &str, code_verifier: &str) -> Result<String, OidcError> {
+        let (Some(redirect), Some(secret)) = (&self.config.redirect_url, &self.config.client_secret) else {
+            return Err(OidcError::Provider("dashboard login not configured".to_string()));
+        };
+        let metadata = self.metadata().await?;
+        let response = self
+            .http
+            .post(&metadata.token_endpoint)
+            .basic_auth(&self.config.client_id, Some(secret))
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", redirect.as_str()),
+                ("code_verifier", code_verifier),
+            ])
+            .send()
+            .await
+            .map_err(|e| OidcError::Provider(format!("token endpoint: {}", e)))?;
+        if !response.status().is_success() {
+            return Err(OidcError::Provider(format!("token endpoint: HTTP {}", response.status())));
+        }
+        let body: TokenResponse = response
+            .json()
+            .await
+            .map_err(|e| OidcError::Provider(format!("token endpoint: invalid JSON: {}", e)))?;
+        body.id_token
+            .ok_or_else(|| OidcError::Provider("token endpoint returned no id_token".to_string()))
+    }
+}
+
+/// PKCE S256 challenge of a code verifier (RFC 7636).
+pub fn pkce_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+fn decode_segment<T: serde::de::DeserializeOwned>(segment: &str, what: &str) -> Result<T, OidcError> {
+    let bytes = URL_SAFE_NO_PAD
+        .decode(segment)
+        .map_err(|e| OidcError::Malformed(format!("{} encoding: {}", what, e)))?;
+    serde_json::from_slice(&bytes).map_err(|e| OidcError::Malformed(format!("{}: {}", what, e)))
+}
+
+fn timestamp_claim(claims: &JsonValue, name: &str) -> Result<Option<DateTime<Utc>>, OidcError> {
+    let Some(value) = claims.get(name) else { return Ok(None) };
+    let secs = value
+        .as_i64()
+        .or_else(|| value.as_f64().map(|f| f as i64))
+        .ok_or_else(|| OidcError::InvalidClaim(format!("{} is not a number", name)))?;
+    Utc.timestamp_opt(secs, 0)
+        .single()
+        .map(Some)
+        .ok_or_else(|| OidcError::InvalidClaim(format!("{} out of range", name)))
+}
+
+/// Groups under a (dotted) claim path: an array of strings or a single string.
+fn groups_of(claims: &JsonValue, path: &str) -> Vec<String> {
+    let value = path.split('.').try_fold(claims, |v, key| v.get(key));
+    match value {
+        Some(JsonValue::Array(items)) => items.iter().filter_map(|g| g.as_str().map(str::to_string)).collect(),
+        Some(JsonValue::String(group)) => vec![group.clone()],
+        _ => Vec::new(),
+    }
+}
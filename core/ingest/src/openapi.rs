@@ -0,0 +1,356 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/openapi.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Generated OpenAPI 3.1 contract for the ingest HTTP API, served with a vendored Swagger UI when RANSOMEYE_INGEST_OPENAPI is enabled
+
+use axum::Router;
+use health::{HealthReport, HealthStatus, SubsystemHealth};
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::agent_log::{Continuity, LogSegment, LogSegmentReceipt};
+use crate::annotation::{Annotation, AnnotationRevision, AnnotationSubject};
+use crate::audit_review::{AuditReviewAttestation, AuditReviewCoverage, ReviewPeriod, ReviewerAuth};
+use crate::dashboard_cache::{DashboardCacheStats, DetectionsBySeverity, EventsPerMinute, FleetOverview, QueryCacheStats};
+use crate::drop_accounting::IngestDropStats;
+use crate::envelope_validation::{ValidationCategory, ValidationError};
+use crate::export::{ExportStats, ExportedEvent};
+use crate::feature_flags::{FlagSetting, FlagSource};
+use crate::http_agent_auth::{EnrollRequest, KeyRotateRequest, KeyRotateResponse, RevokeRequest, RevokeResponse, TokenResponse};
+use crate::http_annotation_admin::{AnnotationChangeResponse, CreateAnnotationRequest, EditAnnotationRequest};
+use crate::http_audit_review_admin::AuditReviewRequest;
+use crate::http_feature_flags_admin::{
+    ClearFeatureFlagRequest, ClearFeatureFlagResponse, FeatureFlagList, FeatureFlagStatus, SetFeatureFlagRequest,
+    SetFeatureFlagResponse,
+};
+use crate::http_identity_admin::{ResolveConflictRequest, ResolveConflictResponse};
+use crate::http_legal_hold_admin::{PlaceHoldRequest, PlaceHoldResponse, ReleaseHoldRequest, ReleaseHoldResponse};
+use crate::http_list::Page;
+use crate::http_maintenance_admin::{
+    CancelMaintenanceWindowRequest, CancelMaintenanceWindowResponse, CreateMaintenanceWindowResponse,
+};
+use crate::http_memory_acquisition::{
+    AcquisitionCompleteRequest, AcquisitionCompleteResponse, AcquisitionFailRequest, AcquisitionFailResponse,
+    AcquisitionRequestResponse, ChunkUploadResponse,
+};
+use crate::http_operator_auth::{BreakGlassLoginRequest, OperatorSessionResponse};
+use crate::http_pcap_capture::{CaptureFailRequest, CaptureFailResponse, CaptureUploadResponse};
+use crate::http_rule_metrics_admin::{CloseIncidentRequest, CloseIncidentResponse};
+use crate::http_runtime_admin::RuntimeConfigRequest;
+use crate::http_sandbox::{SandboxSubmitRequest, SandboxSubmitResponse};
+use crate::http_schema_admin::{SchemaMigration, SchemaStatus, SchemaValidation, TableSize};
+use crate::http_server::{AppState, IngestResponse};
+use crate::http_suppression_admin::{
+    CreateSuppressionRequest, CreateSuppressionResponse, ExpireSuppressionRequest, ExpireSuppressionResponse,
+};
+use crate::http_webhook_admin::{
+    DisableWebhookRequest, RedriveDeliveryRequest, RegisterWebhookRequest, RegisterWebhookResponse, WebhookChangeResponse,
+};
+use crate::http_yara::{CreateRulePackRequest, CreateRulePackResponse, ScanRequestResponse};
+use crate::legal_hold::{HoldSubject, LegalHold};
+use crate::load_shedding::LoadShedStats;
+use crate::maintenance::{
+    DetectionClass, HeldBackClass, HeldBackDetection, MaintenanceAction, MaintenanceReport, MaintenanceScope,
+    MaintenanceWindow, MaintenanceWindowSpec, ScopeKind,
+};
+use crate::memory_acquisition::{AcquisitionOrder, AcquisitionRequest, AcquisitionScope, MemoryAcquisition};
+use crate::operator_auth::{OperatorAuthMethod, OperatorIdentity, OperatorRole};
+use crate::pcap_capture::{CaptureOrder, PcapCapture};
+use crate::pipeline::{PipelineStats, ShardStats};
+use crate::retention_runs::{RetentionAlert, RetentionAlertKind, RetentionRun, RetentionStatus, RunMode, RunStatus};
+use crate::rule_metrics::{Disposition, RuleMetrics, RuleTuningReport, TuningEntry, TuningThresholds};
+use crate::runtime_controls::RuntimeState;
+use crate::sandbox::SandboxSubmission;
+use crate::service_heartbeat::OrchestratorLinkStatus;
+use crate::storage::{
+    AgentTypeCount, ConflictResolution, DiskEncryption, DropOrigin, DropSummary, EventRate, FleetSummary, HostInventoryRecord,
+    IdentityConflictRecord, IdentityOrigin, SecurityTool, SeverityCount,
+};
+use crate::suppression::{Suppression, SuppressionMatch};
+use crate::webhooks::{DeliveryStatus, WebhookDelivery, WebhookSubscription};
+use crate::yara_rules::{
+    RulePackOrder, ScanOrder, ScanRequest, YaraPollRequest, YaraPollResponse, YaraRulePackEntry, YaraScan,
+};
+
+pub const OPENAPI_JSON_PATH: &str = "/openapi.json";
+pub const SWAGGER_UI_PATH: &str = "/swagger-ui";
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "RansomEye Ingest API", description = "Signed telemetry ingestion, agent credentials and ingest administration."),
+    paths(
+        crate::http_server::handle_linux_ingest,
+        crate::http_server::handle_dpi_ingest,
+        crate::http_agent_auth::handle_enroll,
+        crate::http_agent_auth::handle_rotate,
+        crate::http_agent_auth::handle_revoke,
+        crate::http_agent_auth::handle_key_rotate,
+        crate::http_runtime_admin::handle_get_runtime_config,
+        crate::http_runtime_admin::handle_set_runtime_config,
+        crate::http_runtime_admin::handle_get_orchestrator_link,
+        crate::http_runtime_admin::handle_get_pipeline,
+        crate::http_runtime_admin::handle_get_health,
+        crate::http_runtime_admin::handle_get_load_shedding,
+        crate::http_drops_admin::handle_list_drops,
+        crate::http_drops_admin::handle_get_ingest_drops,
+        crate::http_export_admin::handle_export_events,
+        crate::http_export_admin::handle_get_export_stats,
+        crate::http_fleet_admin::handle_list_inventory,
+        crate::http_dashboard_admin::handle_detections_by_severity,
+        crate::http_dashboard_admin::handle_events_per_minute,
+        crate::http_dashboard_admin::handle_fleet_summary,
+        crate::http_dashboard_admin::handle_get_cache_stats,
+        crate::http_identity_admin::handle_list_conflicts,
+        crate::http_identity_admin::handle_resolve_conflict,
+        crate::http_webhook_admin::handle_register,
+        crate::http_webhook_admin::handle_list_webhooks,
+        crate::http_webhook_admin::handle_disable_webhook,
+        crate::http_webhook_admin::handle_list_deliveries,
+        crate::http_webhook_admin::handle_redrive_delivery,
+        crate::http_legal_hold_admin::handle_place_hold,
+        crate::http_legal_hold_admin::handle_list_holds,
+        crate::http_legal_hold_admin::handle_release_hold,
+        crate::http_suppression_admin::handle_create_suppression,
+        crate::http_suppression_admin::handle_list_suppressions,
+        crate::http_suppression_admin::handle_expire_suppression,
+        crate::http_maintenance_admin::handle_create_window,
+        crate::http_maintenance_admin::handle_list_windows,
+        crate::http_maintenance_admin::handle_cancel_window,
+        crate::http_maintenance_admin::handle_window_report,
+        crate::http_feature_flags_admin::handle_list_flags,
+        crate::http_feature_flags_admin::handle_set_flag,
+        crate::http_feature_flags_admin::handle_clear_flag,
+        crate::http_annotation_admin::handle_create_annotation,
+        crate::http_annotation_admin::handle_edit_annotation,
+        crate::http_annotation_admin::handle_list_annotations,
+        crate::http_audit_review_admin::handle_attest_review,
+        crate::http_audit_review_admin::handle_list_reviews,
+        crate::http_audit_review_admin::handle_review_coverage,
+        crate::http_rule_metrics_admin::handle_close_incident,
+        crate::http_rule_metrics_admin::handle_list_rule_metrics,
+        crate::http_rule_metrics_admin::handle_rule_tuning,
+        crate::http_retention_admin::handle_list_runs,
+        crate::http_retention_admin::handle_retention_status,
+        crate::http_pcap_capture::handle_claim_capture,
+        crate::http_pcap_capture::handle_upload_capture,
+        crate::http_pcap_capture::handle_fail_capture,
+        crate::http_pcap_capture::handle_list_captures,
+        crate::http_memory_acquisition::handle_request_acquisition,
+        crate::http_memory_acquisition::handle_list_acquisitions,
+        crate::http_memory_acquisition::handle_claim_acquisition,
+        crate::http_memory_acquisition::handle_upload_chunk,
+        crate::http_memory_acquisition::handle_complete_acquisition,
+        crate::http_memory_acquisition::handle_fail_acquisition,
+        crate::http_agent_log::handle_upload_segment,
+        crate::http_sandbox::handle_submit_sample,
+        crate::http_sandbox::handle_submit_hash,
+        crate::http_sandbox::handle_list_submissions,
+        crate::http_yara::handle_create_rule_pack,
+        crate::http_yara::handle_list_rule_packs,
+        crate::http_yara::handle_request_scan,
+        crate::http_yara::handle_list_scans,
+        crate::http_yara::handle_poll,
+        crate::http_schema_admin::handle_get_schema,
+        crate::http_operator_auth::handle_oidc_login,
+        crate::http_operator_auth::handle_oidc_callback,
+        crate::http_operator_auth::handle_break_glass_login,
+        crate::http_operator_auth::handle_get_session,
+    ),
+    components(schemas(
+        IngestResponse,
+        ValidationError,
+        ValidationCategory,
+        EnrollRequest,
+        TokenResponse,
+        RevokeRequest,
+        RevokeResponse,
+        KeyRotateRequest,
+        KeyRotateResponse,
+        RuntimeConfigRequest,
+        RuntimeState,
+        OrchestratorLinkStatus,
+        PipelineStats,
+        ShardStats,
+        LoadShedStats,
+        HealthReport,
+        SubsystemHealth,
+        HealthStatus,
+        DropSummary,
+        DropOrigin,
+        IngestDropStats,
+        ExportedEvent,
+        ExportStats,
+        HostInventoryRecord,
+        SecurityTool,
+        DiskEncryption,
+        DetectionsBySeverity,
+        SeverityCount,
+        EventsPerMinute,
+        EventRate,
+        FleetOverview,
+        FleetSummary,
+        AgentTypeCount,
+        DashboardCacheStats,
+        QueryCacheStats,
+        Page,
+        IdentityConflictRecord,
+        IdentityOrigin,
+        ConflictResolution,
+        ResolveConflictRequest,
+        ResolveConflictResponse,
+        RegisterWebhookRequest,
+        RegisterWebhookResponse,
+        DisableWebhookRequest,
+        RedriveDeliveryRequest,
+        WebhookChangeResponse,
+        WebhookSubscription,
+        WebhookDelivery,
+        DeliveryStatus,
+        PlaceHoldRequest,
+        PlaceHoldResponse,
+        ReleaseHoldRequest,
+        ReleaseHoldResponse,
+        LegalHold,
+        HoldSubject,
+        CreateSuppressionRequest,
+        CreateSuppressionResponse,
+        ExpireSuppressionRequest,
+        ExpireSuppressionResponse,
+        Suppression,
+        SuppressionMatch,
+        MaintenanceWindowSpec,
+        MaintenanceScope,
+        ScopeKind,
+        MaintenanceAction,
+        DetectionClass,
+        CreateMaintenanceWindowResponse,
+        CancelMaintenanceWindowRequest,
+        CancelMaintenanceWindowResponse,
+        MaintenanceWindow,
+        MaintenanceReport,
+        HeldBackClass,
+        HeldBackDetection,
+        FeatureFlagList,
+        FeatureFlagStatus,
+        FlagSetting,
+        FlagSource,
+        SetFeatureFlagRequest,
+        SetFeatureFlagResponse,
+        ClearFeatureFlagRequest,
+        ClearFeatureFlagResponse,
+        CreateAnnotationRequest,
+        EditAnnotationRequest,
+        AnnotationChangeResponse,
+        Annotation,
+        AnnotationRevision,
+        AnnotationSubject,
+        AuditReviewRequest,
+        AuditReviewAttestation,
+        AuditReviewCoverage,
+        ReviewPeriod,
+        ReviewerAuth,
+        CloseIncidentRequest,
+        CloseIncidentResponse,
+        Disposition,
+        RuleMetrics,
+        RuleTuningReport,
+        TuningEntry,
+        TuningThresholds,
+        RetentionRun,
+        RunMode,
+        RunStatus,
+        RetentionStatus,
+        RetentionAlert,
+        RetentionAlertKind,
+        CaptureOrder,
+        CaptureUploadResponse,
+        CaptureFailRequest,
+        CaptureFailResponse,
+        PcapCapture,
+        AcquisitionRequest,
+        AcquisitionRequestResponse,
+        AcquisitionScope,
+        AcquisitionOrder,
+        ChunkUploadResponse,
+        AcquisitionCompleteRequest,
+        AcquisitionCompleteResponse,
+        AcquisitionFailRequest,
+        AcquisitionFailResponse,
+        MemoryAcquisition,
+        LogSegment,
+        LogSegmentReceipt,
+        Continuity,
+        SandboxSubmitRequest,
+        SandboxSubmitResponse,
+        SandboxSubmission,
+        CreateRulePackRequest,
+        CreateRulePackResponse,
+        YaraRulePackEntry,
+        ScanRequest,
+        ScanRequestResponse,
+        YaraScan,
+        YaraPollRequest,
+        YaraPollResponse,
+        RulePackOrder,
+        ScanOrder,
+        SchemaStatus,
+        SchemaMigration,
+        SchemaValidation,
+        TableSize,
+        BreakGlassLoginRequest,
+        OperatorSessionResponse,
+        OperatorIdentity,
+        OperatorRole,
+        OperatorAuthMethod,
+    )),
+    modifiers(&SecuritySchemes),
+    tags(
+        (name = "ingest", description = "Signed event ingestion (agent bearer token)"),
+        (name = "agents", description = "Agent enrollment, token and signing key lifecycle"),
+        (name = "probes", description = "DPI probe control channel (agent bearer token)"),
+        (name = "admin", description = "Operator endpoints (X-Admin-Key, or an operator bearer token whose role permits the route)"),
+        (name = "auth", description = "Operator sign-in (OIDC dashboard login, break-glass accounts)"),
+    )
+)]
+pub struct IngestApiDoc;
+
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "agent_token",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+        components.add_security_scheme(
+            "enrollment_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-Enrollment-Key"))),
+        );
+        components.add_security_scheme(
+            "admin_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-Admin-Key"))),
+        );
+        components.add_security_scheme(
+            "operator_bearer",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).bearer_format("JWT").build()),
+        );
+    }
+}
+
+/// Generated contract.
+pub fn openapi() -> utoipa::openapi::OpenApi {
+    IngestApiDoc::openapi()
+}
+
+/// Routes serving the contract and Swagger UI. Mounted only when RANSOMEYE_INGEST_OPENAPI is enabled.
+pub fn router() -> Router<AppState> {
+    Router::new().merge(SwaggerUi::new(SWAGGER_UI_PATH).url(OPENAPI_JSON_PATH, openapi()))
+}
+
+/// RANSOMEYE_INGEST_OPENAPI=true|1 (default off).
+pub fn enabled_from_env() -> bool {
+    std::env::var("RANSOMEYE_INGEST_OPENAPI")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
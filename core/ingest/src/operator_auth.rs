@@ -0,0 +1,641 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/operator_auth.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Operator authentication alongside the admin key - OIDC bearer tokens and signed operator sessions mapped to viewer/analyst/admin roles by IdP group, local break-glass accounts with lockout and per-request audit, and the admin route middleware enforcing the role
+
+/*
+ * Operator Authentication
+ *
+ * The admin API (every /admin route and /schema) accepts, in addition to X-Admin-Key:
+ *
+ * - Authorization: Bearer <IdP access token> - an RS256 JWT from the configured OIDC issuer,
+ *   validated per request; the user's groups map to a role (RANSOMEYE_OIDC_ROLE_MAP).
+ * - Authorization: Bearer <session> - an operator session issued by this service after a
+ *   dashboard OIDC login (/auth/oidc/login) or a break-glass login (/auth/break-glass). Sessions
+ *   are HMAC-signed with RANSOMEYE_OPERATOR_SESSION_KEY_PATH, shared by every ingest instance;
+ *   the role is fixed at login, so IdP group changes apply from the next login.
+ *
+ * Roles: viewer reads (GET), analyst additionally runs investigations (annotations, identity
+ * conflict resolution, memory acquisitions, sandbox submissions, YARA scans), admin may do
+ * everything the admin key can. Audit review attestations are open to every role and gated by
+ * RANSOMEYE_AUDIT_REVIEWERS instead. A token whose groups map to no role is refused (403).
+ *
+ * Break-glass accounts are for an IdP outage: PBKDF2-hashed local credentials, a mandatory
+ * reason, lockout after repeated failures, short sessions, and every login attempt and every
+ * request made with such a session written to the audit chain (fail-closed: no audit, no access).
+ *
+ * The middleware tells the admin handlers an operator was authorized by setting an internal
+ * header carrying a per-process secret; a client-supplied copy is always stripped first.
+ */
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{Request, State},
+    http::{HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::{DateTime, Utc};
+use crypto::hmac::HmacSha256Key;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::json;
+use thiserror::Error;
+use tracing::{error, info, warn};
+use url::Url;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::http_agent_auth;
+use crate::oidc::{self, OidcConfig, OidcError, OidcProvider};
+use crate::storage::TelemetryStore;
+
+/// Internal header set by [`authorize`] for [`crate::http_runtime_admin::AdminKey`]
+pub const GRANT_HEADER: &str = "x-ransomeye-operator-grant";
+const SESSION_PREFIX: &str = "res1";
+const MIN_KEY_LEN: usize = 32;
+const DEFAULT_SESSION_TTL_SECS: u64 = 3600;
+const DEFAULT_BREAK_GLASS_TTL_SECS: u64 = 900;
+/// Stored break-glass hashes must use at least this many PBKDF2 iterations
+pub const MIN_PBKDF2_ITERATIONS: u32 = 100_000;
+/// Failed break-glass logins per account before it locks
+pub const MAX_BREAK_GLASS_FAILURES: usize = 5;
+pub const BREAK_GLASS_LOCKOUT: Duration = Duration::from_secs(15 * 60);
+
+/// Non-read admin routes analysts may call.
+const ANALYST_ACTIONS: &[&str] = &[
+    "/admin/annotations",
+    "/admin/annotations/edit",
+    "/admin/identity-conflicts/resolve",
+    "/admin/incidents/close",
+    "/admin/memory-acquisitions",
+    "/admin/sandbox-submissions",
+    "/admin/yara-scans",
+];
+
+/// Non-read admin routes any role may call; the handler checks its own list of who may act.
+const ANY_ROLE_ACTIONS: &[&str] = &["/admin/audit-reviews"];
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum OperatorAuthError {
+    #[error("Malformed session: {0}")]
+    Malformed(String),
+    #[error("Session signature verification failed")]
+    BadSignature,
+    #[error("Session expired at {0}")]
+    Expired(DateTime<Utc>),
+    #[error("Token rejected: {0}")]
+    Token(OidcError),
+    #[error("No role mapped for {0}")]
+    NoRole(String),
+    #[error("Invalid break-glass credentials")]
+    InvalidCredentials,
+    #[error("Break-glass account '{0}' locked after repeated failures")]
+    LockedOut(String),
+    #[error("Operator authentication not configured: {0}")]
+    NotConfigured(String),
+}
+
+impl OperatorAuthError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            OperatorAuthError::NoRole(_) => StatusCode::FORBIDDEN,
+            OperatorAuthError::LockedOut(_) => StatusCode::TOO_MANY_REQUESTS,
+            OperatorAuthError::Token(OidcError::Provider(_)) | OperatorAuthError::NotConfigured(_) => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            _ => StatusCode::UNAUTHORIZED,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OperatorRole {
+    Viewer,
+    Analyst,
+    Admin,
+}
+
+impl OperatorRole {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "viewer" => Some(OperatorRole::Viewer),
+            "analyst" => Some(OperatorRole::Analyst),
+            "admin" => Some(OperatorRole::Admin),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OperatorRole::Viewer => "viewer",
+            OperatorRole::Analyst => "analyst",
+            OperatorRole::Admin => "admin",
+        }
+    }
+
+    /// Whether the role may call `method path` on the admin API.
+    pub fn permits(&self, method: &Method, path: &str) -> bool {
+        let read = method == Method::GET || method == Method::HEAD;
+        let any_role = ANY_ROLE_ACTIONS.contains(&path);
+        match self {
+            OperatorRole::Admin => true,
+            OperatorRole::Analyst => read || any_role || ANALYST_ACTIONS.contains(&path),
+            OperatorRole::Viewer => read || any_role,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OperatorAuthMethod {
+    /// IdP bearer token or a session from a dashboard OIDC login
+    Oidc,
+    BreakGlass,
+}
+
+/// Authenticated operator (inserted as a request extension on admin routes).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct OperatorIdentity {
+    /// IdP subject, or the break-glass username
+    pub subject: String,
+    pub name: String,
+    pub role: OperatorRole,
+    pub method: OperatorAuthMethod,
+    pub expires_at: DateTime<Utc>,
+    /// Set for sessions issued by this service
+    pub session_id: Option<Uuid>,
+    /// Justification given at a break-glass login
+    pub reason: Option<String>,
+}
+
+/// IdP group -> role (RANSOMEYE_OIDC_ROLE_MAP: "group=role,..."); several matches take the highest.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RoleMap(Vec<(String, OperatorRole)>);
+
+impl RoleMap {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut entries = Vec::new();
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (group, role) = entry
+                .rsplit_once('=')
+                .ok_or_else(|| format!("Invalid role mapping '{}' (expected group=role)", entry))?;
+            let role = OperatorRole::parse(role.trim())
+                .ok_or_else(|| format!("Invalid role '{}' for group '{}' (expected viewer|analyst|admin)", role.trim(), group))?;
+            if group.trim().is_empty() {
+                return Err(format!("Invalid role mapping '{}' (empty group)", entry));
+            }
+            entries.push((group.trim().to_string(), role));
+        }
+        Ok(Self(entries))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn role_for<'a>(&self, groups: impl IntoIterator<Item = &'a str>) -> Option<OperatorRole> {
+        groups
+            .into_iter()
+            .flat_map(|g| self.0.iter().filter(move |(group, _)| group == g).map(|(_, role)| *role))
+            .max()
+    }
+}
+
+/// HMAC-SHA256 sealed, base64url JSON values: operator sessions and OIDC login state.
+/// Format: <kind>.<payload_b64url>.<mac_b64url>, mac over "<kind>.<payload_b64url>".
+pub struct SessionSigner {
+    key: HmacSha256Key,
+}
+
+impl SessionSigner {
+    pub fn new(key_bytes: &[u8]) -> Result<Self, String> {
+        if key_bytes.len() < MIN_KEY_LEN {
+            return Err(format!("operator session key must be at least {} bytes (got {})", MIN_KEY_LEN, key_bytes.len()));
+        }
+        Ok(Self { key: HmacSha256Key::new(key_bytes) })
+    }
+
+    pub fn from_key_file(path: &str) -> Result<Self, String> {
+        let mut bytes = std::fs::read(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+        while bytes.last() == Some(&b'\n') || bytes.last() == Some(&b'\r') {
+            bytes.pop();
+        }
+        Self::new(&bytes)
+    }
+
+    pub fn seal<T: Serialize>(&self, kind: &str, value: &T) -> String {
+        let payload = serde_json::to_vec(value).expect("sealed values serialize");
+        let body = format!("{}.{}", kind, URL_SAFE_NO_PAD.encode(payload));
+        let tag = self.key.sign(body.as_bytes());
+        format!("{}.{}", body, URL_SAFE_NO_PAD.encode(tag))
+    }
+
+    pub fn open<T: DeserializeOwned>(&self, kind: &str, sealed: &str) -> Result<T, OperatorAuthError> {
+        let parts: Vec<&str> = sealed.split('.').collect();
+        if parts.len() != 3 || parts[0] != kind {
+            return Err(OperatorAuthError::Malformed(format!("expected {}.<payload>.<mac>", kind)));
+        }
+        let mac = URL_SAFE_NO_PAD
+            .decode(parts[2])
+            .map_err(|e| OperatorAuthError::Malformed(format!("mac encoding: {}", e)))?;
+        let body_len = sealed.len() - parts[2].len() - 1;
+        self.key
+            .verify(&sealed.as_bytes()[..body_len], &mac)
+            .map_err(|_| OperatorAuthError::BadSignature)?;
+        let payload = URL_SAFE_NO_PAD
+            .decode(parts[1])
+            .map_err(|e| OperatorAuthError::Malformed(format!("payload encoding: {}", e)))?;
+        serde_json::from_slice(&payload).map_err(|e| OperatorAuthError::Malformed(format!("payload: {}", e)))
+    }
+
+    pub fn issue(&self, identity: &OperatorIdentity) -> String {
+        self.seal(SESSION_PREFIX, identity)
+    }
+
+    pub fn verify(&self, token: &str, now: DateTime<Utc>) -> Result<OperatorIdentity, OperatorAuthError> {
+        let identity: OperatorIdentity = self.open(SESSION_PREFIX, token)?;
+        if identity.expires_at <= now {
+            return Err(OperatorAuthError::Expired(identity.expires_at));
+        }
+        Ok(identity)
+    }
+}
+
+pub fn is_session_token(token: &str) -> bool {
+    token.starts_with(SESSION_PREFIX) && token.as_bytes().get(SESSION_PREFIX.len()) == Some(&b'.')
+}
+
+/// Local emergency account (one line of RANSOMEYE_BREAK_GLASS_ACCOUNTS_PATH:
+/// `username:role:pbkdf2-sha256$<iterations>$<salt_b64url>$<hash_b64url>`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BreakGlassAccount {
+    pub username: String,
+    pub role: OperatorRole,
+    iterations: u32,
+    salt: Vec<u8>,
+    hash: Vec<u8>,
+}
+
+impl BreakGlassAccount {
+    pub fn parse_line(line: &str) -> Result<Self, String> {
+        let mut fields = line.splitn(3, ':');
+        let (Some(username), Some(role), Some(encoded)) = (fields.next(), fields.next(), fields.next()) else {
+            return Err("expected username:role:pbkdf2-sha256$iterations$salt$hash".to_string());
+        };
+        if username.is_empty() {
+            return Err("empty username".to_string());
+        }
+        let role = OperatorRole::parse(role).ok_or_else(|| format!("invalid role '{}' for '{}'", role, username))?;
+        let parts: Vec<&str> = encoded.split('$').collect();
+        if parts.len() != 4 || parts[0] != "pbkdf2-sha256" {
+            return Err(format!("'{}': hash must be pbkdf2-sha256$iterations$salt$hash", username));
+        }
+        let iterations = parts[1]
+            .parse::<u32>()
+            .map_err(|_| format!("'{}': invalid iteration count '{}'", username, parts[1]))?;
+        if iterations < MIN_PBKDF2_ITERATIONS {
+            return Err(format!("'{}': {} iterations is below the minimum of {}", username, iterations, MIN_PBKDF2_ITERATIONS));
+        }
+        let salt = URL_SAFE_NO_PAD.decode(parts[2]).map_err(|_| format!("'{}': invalid salt encoding", username))?;
+        let hash = URL_SAFE_NO_PAD.decode(parts[3]).map_err(|_| format!("'{}': invalid hash encoding", username))?;
+        if salt.len() < 16 || hash.len() != 32 {
+            return Err(format!("'{}': salt must be at least 16 bytes and the hash 32", username));
+        }
+        Ok(Self { username: username.to_string(), role, iterations, salt, hash })
+    }
+
+    fn verify(&self, password: &str) -> bool {
+        crypto::kdf::pbkdf2_sha256(self.iterations, &self.salt, password.as_bytes())
+            .is_ok_and(|hash| crypto::constant_time_eq(&hash, &self.hash))
+    }
+}
+
+/// Stored form of a break-glass password (for provisioning the accounts file).
+pub fn hash_password(password: &str, iterations: u32) -> Result<String, String> {
+    let mut salt = [0u8; 16];
+    crypto::rand::fill(&mut salt).map_err(|e| e.to_string())?;
+    let hash = crypto::kdf::pbkdf2_sha256(iterations, &salt, password.as_bytes()).map_err(|e| e.to_string())?;
+    Ok(format!("pbkdf2-sha256${}${}${}", iterations, URL_SAFE_NO_PAD.encode(salt), URL_SAFE_NO_PAD.encode(hash)))
+}
+
+pub struct BreakGlass {
+    accounts: HashMap<String, BreakGlassAccount>,
+    /// Recent failures per username (lockout window)
+    failures: Mutex<HashMap<String, Vec<Instant>>>,
+    pub session_ttl: Duration,
+}
+
+impl BreakGlass {
+    pub fn new(accounts: Vec<BreakGlassAccount>, session_ttl: Duration) -> Self {
+        Self {
+            accounts: accounts.into_iter().map(|a| (a.username.clone(), a)).collect(),
+            failures: Mutex::new(HashMap::new()),
+            session_ttl,
+        }
+    }
+
+    pub fn load(path: &str, session_ttl: Duration) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+        let mut accounts = Vec::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            accounts.push(BreakGlassAccount::parse_line(line).map_err(|e| format!("{} line {}: {}", path, n + 1, e))?);
+        }
+        if accounts.is_empty() {
+            return Err(format!("{} defines no accounts", path));
+        }
+        Ok(Self::new(accounts, session_ttl))
+    }
+
+    pub fn usernames(&self) -> Vec<&str> {
+        self.accounts.keys().map(String::as_str).collect()
+    }
+
+    /// Check a login. A locked account is refused even with the right password; unknown
+    /// usernames count and cost the same as wrong passwords.
+    pub fn authenticate(&self, username: &str, password: &str, now: Instant) -> Result<OperatorRole, OperatorAuthError> {
+        {
+            let mut failures = self.failures.lock().unwrap_or_else(|e| e.into_inner());
+            let recent = failures.entry(username.to_string()).or_default();
+            recent.retain(|at| now.saturating_duration_since(*at) < BREAK_GLASS_LOCKOUT);
+            if recent.len() >= MAX_BREAK_GLASS_FAILURES {
+                return Err(OperatorAuthError::LockedOut(username.to_string()));
+            }
+        }
+        let verified = match self.accounts.get(username) {
+            Some(account) => account.verify(password).then_some(account.role),
+            None => {
+                let _ = crypto::kdf::pbkdf2_sha256(MIN_PBKDF2_ITERATIONS, &[0u8; 16], password.as_bytes());
+                None
+            }
+        };
+        let mut failures = self.failures.lock().unwrap_or_else(|e| e.into_inner());
+        match verified {
+            Some(role) => {
+                failures.remove(username);
+                Ok(role)
+            }
+            None => {
+                failures.entry(username.to_string()).or_default().push(now);
+                Err(OperatorAuthError::InvalidCredentials)
+            }
+        }
+    }
+}
+
+/// Operator authentication of this instance (disabled: admin key only).
+pub struct OperatorAuth {
+    pub oidc: Option<OidcProvider>,
+    pub role_map: RoleMap,
+    pub break_glass: Option<BreakGlass>,
+    pub sessions: Option<SessionSigner>,
+    pub session_ttl: Duration,
+    /// After a dashboard login the browser is sent to <dashboard_url>#session=<token>
+    pub dashboard_url: Option<Url>,
+    grant: String,
+}
+
+impl OperatorAuth {
+    /// Load from environment (FAIL-CLOSED: a partial configuration aborts startup).
+    pub fn from_env() -> Result<Self, String> {
+        let session_ttl = Duration::from_secs(env_secs("RANSOMEYE_OPERATOR_SESSION_TTL_SECS", DEFAULT_SESSION_TTL_SECS)?);
+
+        let (oidc, role_map) = match std::env::var("RANSOMEYE_OIDC_ISSUER") {
+            Ok(issuer) => {
+                let config = oidc_config_from_env(issuer)?;
+                let role_map = RoleMap::parse(&std::env::var("RANSOMEYE_OIDC_ROLE_MAP").unwrap_or_default())?;
+                if role_map.is_empty() {
+                    return Err("FAIL-CLOSED: RANSOMEYE_OIDC_ISSUER requires RANSOMEYE_OIDC_ROLE_MAP (group=role,...)".to_string());
+                }
+                info!(
+                    "Operator OIDC: issuer={} | audience={} | dashboard_login={} | groups_claim={} | role mappings={}",
+                    config.issuer,
+                    config.audience,
+                    config.redirect_url.is_some(),
+                    config.groups_claim,
+                    role_map.0.len()
+                );
+                (Some(OidcProvider::new(config)?), role_map)
+            }
+            Err(_) => (None, RoleMap::default()),
+        };
+
+        let break_glass = match std::env::var("RANSOMEYE_BREAK_GLASS_ACCOUNTS_PATH") {
+            Ok(path) => {
+                let ttl = env_secs("RANSOMEYE_BREAK_GLASS_SESSION_TTL_SECS", DEFAULT_BREAK_GLASS_TTL_SECS)?;
+                let break_glass = BreakGlass::load(&path, Duration::from_secs(ttl)).map_err(|e| format!("FAIL-CLOSED: {}", e))?;
+                warn!("Break-glass operator accounts ENABLED: {:?} (sessions {}s, every request audited)", break_glass.usernames(), ttl);
+                Some(break_glass)
+            }
+            Err(_) => None,
+        };
+
+        let login = oidc.as_ref().is_some_and(|o| o.config().redirect_url.is_some());
+        let sessions = match std::env::var("RANSOMEYE_OPERATOR_SESSION_KEY_PATH") {
+            Ok(path) => Some(SessionSigner::from_key_file(&path).map_err(|e| format!("FAIL-CLOSED: {}", e))?),
+            Err(_) if login || break_glass.is_some() => {
+                return Err(
+                    "FAIL-CLOSED: dashboard OIDC login and break-glass accounts require RANSOMEYE_OPERATOR_SESSION_KEY_PATH"
+                        .to_string(),
+                )
+            }
+            Err(_) => None,
+        };
+
+        let dashboard_url = match std::env::var("RANSOMEYE_OIDC_DASHBOARD_URL") {
+            Ok(v) => Some(Url::parse(&v).map_err(|e| format!("Invalid RANSOMEYE_OIDC_DASHBOARD_URL '{}': {}", v, e))?),
+            Err(_) => None,
+        };
+
+        Self::new(oidc, role_map, break_glass, sessions, session_ttl, dashboard_url)
+    }
+
+    pub fn new(
+        oidc: Option<OidcProvider>,
+        role_map: RoleMap,
+        break_glass: Option<BreakGlass>,
+        sessions: Option<SessionSigner>,
+        session_ttl: Duration,
+        dashboard_url: Option<Url>,
+    ) -> Result<Self, String> {
+        let mut grant = [0u8; 32];
+        crypto::rand::fill(&mut grant).map_err(|e| format!("FAIL-CLOSED: operator grant: {}", e))?;
+        Ok(Self { oidc, role_map, break_glass, sessions, session_ttl, dashboard_url, grant: hex::encode(grant) })
+    }
+
+    /// Admin key only.
+    pub fn disabled() -> Self {
+        Self {
+            oidc: None,
+            role_map: RoleMap::default(),
+            break_glass: None,
+            sessions: None,
+            session_ttl: Duration::from_secs(DEFAULT_SESSION_TTL_SECS),
+            dashboard_url: None,
+            grant: String::new(),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.oidc.is_some() || self.break_glass.is_some()
+    }
+
+    /// Value of [`GRANT_HEADER`] on requests this middleware authorized (None: disabled).
+    pub fn grant(&self) -> Option<&str> {
+        self.enabled().then_some(self.grant.as_str())
+    }
+
+    /// Resolve a bearer token: a session issued here, else an IdP access token.
+    pub async fn authenticate(&self, token: &str, now: DateTime<Utc>) -> Result<OperatorIdentity, OperatorAuthError> {
+        if is_session_token(token) {
+            let sessions = self
+                .sessions
+                .as_ref()
+                .ok_or_else(|| OperatorAuthError::NotConfigured("no session key".to_string()))?;
+            return sessions.verify(token, now);
+        }
+        let provider = self
+            .oidc
+            .as_ref()
+            .ok_or_else(|| OperatorAuthError::NotConfigured("no OIDC issuer".to_string()))?;
+        let verified = provider.validate_access_token(token, now).await.map_err(OperatorAuthError::Token)?;
+        self.identity_for(&verified, now, None)
+    }
+
+    /// Map a validated IdP token to an operator; a session id is assigned for dashboard logins.
+    pub fn identity_for(
+        &self,
+        verified: &oidc::VerifiedToken,
+        now: DateTime<Utc>,
+        session_id: Option<Uuid>,
+    ) -> Result<OperatorIdentity, OperatorAuthError> {
+        let role = self
+            .role_map
+            .role_for(verified.groups.iter().map(String::as_str))
+            .ok_or_else(|| OperatorAuthError::NoRole(format!("'{}' (groups {:?})", verified.name, verified.groups)))?;
+        let expires_at = match session_id {
+            Some(_) => now + chrono::Duration::from_std(self.session_ttl).unwrap_or(chrono::Duration::hours(1)),
+            None => verified.expires_at,
+        };
+        Ok(OperatorIdentity {
+            subject: verified.subject.clone(),
+            name: verified.name.clone(),
+            role,
+            method: OperatorAuthMethod::Oidc,
+            expires_at,
+            session_id,
+            reason: None,
+        })
+    }
+}
+
+fn oidc_config_from_env(issuer: String) -> Result<OidcConfig, String> {
+    if !issuer.starts_with("https://") {
+        return Err(format!("FAIL-CLOSED: RANSOMEYE_OIDC_ISSUER '{}' must be an https URL", issuer));
+    }
+    let client_id = std::env::var("RANSOMEYE_OIDC_CLIENT_ID")
+        .map_err(|_| "FAIL-CLOSED: RANSOMEYE_OIDC_ISSUER requires RANSOMEYE_OIDC_CLIENT_ID".to_string())?;
+    let redirect_url = match std::env::var("RANSOMEYE_OIDC_REDIRECT_URL") {
+        Ok(v) => Some(Url::parse(&v).map_err(|e| format!("Invalid RANSOMEYE_OIDC_REDIRECT_URL '{}': {}", v, e))?),
+        Err(_) => None,
+    };
+    let client_secret = match std::env::var("RANSOMEYE_OIDC_CLIENT_SECRET_PATH") {
+        Ok(path) => Some(
+            std::fs::read_to_string(&path)
+                .map_err(|e| format!("FAIL-CLOSED: cannot read OIDC client secret {}: {}", path, e))?
+                .trim()
+                .to_string(),
+        ),
+        Err(_) => None,
+    };
+    if redirect_url.is_some() && client_secret.is_none() {
+        return Err("FAIL-CLOSED: RANSOMEYE_OIDC_REDIRECT_URL requires RANSOMEYE_OIDC_CLIENT_SECRET_PATH".to_string());
+    }
+    Ok(OidcConfig {
+        issuer,
+        audience: std::env::var("RANSOMEYE_OIDC_AUDIENCE").unwrap_or_else(|_| client_id.clone()),
+        client_id,
+        client_secret,
+        redirect_url,
+        groups_claim: std::env::var("RANSOMEYE_OIDC_GROUPS_CLAIM").unwrap_or_else(|_| oidc::DEFAULT_GROUPS_CLAIM.to_string()),
+        scopes: std::env::var("RANSOMEYE_OIDC_SCOPES").unwrap_or_else(|_| oidc::DEFAULT_SCOPES.to_string()),
+    })
+}
+
+fn env_secs(key: &str, default_value: u64) -> Result<u64, String> {
+    match std::env::var(key) {
+        Ok(v) => v.parse::<u64>().ok().filter(|s| *s > 0).ok_or_else(|| format!("Invalid {} '{}' (expected seconds > 0)", key, v)),
+        Err(_) => Ok(default_value),
+    }
+}
+
+pub(crate) fn bearer(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+}
+
+/// State of the admin route middleware.
+#[derive(Clone)]
+pub struct OperatorGate {
+    pub auth: Arc<OperatorAuth>,
+    /// Audit chain for break-glass requests
+    pub store: Arc<dyn TelemetryStore>,
+}
+
+/// Middleware for admin routes: authorize a bearer operator for the route's method and path.
+/// Requests with X-Admin-Key, or without a bearer token, reach the handler's admin key check.
+pub async fn authorize(State(gate): State<OperatorGate>, mut request: Request, next: Next) -> Result<Response, StatusCode> {
+    request.headers_mut().remove(GRANT_HEADER);
+    let Some(grant) = gate.auth.grant() else { return Ok(next.run(request).await) };
+    if request.headers().contains_key("x-admin-key") {
+        return Ok(next.run(request).await);
+    }
+    let Some(token) = bearer(request.headers()) else { return Ok(next.run(request).await) };
+
+    let identity = gate.auth.authenticate(token, Utc::now()).await.map_err(|e| {
+        warn!("OPERATOR AUTH REJECT: {}", e);
+        e.status()
+    })?;
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    if !identity.role.permits(&method, &path) {
+        warn!("Operator '{}' ({}) denied {} {}", identity.name, identity.role.as_str(), method, path);
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    if identity.method == OperatorAuthMethod::BreakGlass {
+        let payload = json!({
+            "username": identity.subject,
+            "role": identity.role.as_str(),
+            "session_id": identity.session_id,
+            "reason": identity.reason,
+            "method": method.as_str(),
+            "path": path,
+            "query": request.uri().query(),
+        });
+        // FAIL-CLOSED: a break-glass request that cannot be audited is not served
+        http_agent_auth::audit(gate.store.as_ref(), None, "OPERATOR_BREAK_GLASS_REQUEST", identity.session_id, &payload)
+            .await
+            .map_err(|_| {
+                error!("Break-glass request {} {} by '{}' refused: audit write failed", method, path, identity.subject);
+                StatusCode::SERVICE_UNAVAILABLE
+            })?;
+        warn!("BREAK-GLASS request by '{}': {} {}", identity.subject, method, path);
+    }
+
+    let grant = HeaderValue::from_str(grant).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    request.headers_mut().insert(GRANT_HEADER, grant);
+    request.extensions_mut().insert(identity);
+    Ok(next.run(request).await)
+}
@@ -19,22 +19,29 @@ use tracing::{warn, debug};
 use crate::protocol::event_envelope::EventEnvelope;
 use crate::config::Config;
 use crate::security::replay_protection::ReplayProtector;
+use crate::shared_state::{LocalReplayState, ReplayStateBackend};
+
+/// Replay scope of the ordering check (the authentication path checks the same events under its own)
+pub const ORDERING_REPLAY_SCOPE: &str = "ordering";
 
 pub struct OrderingManager {
     config: Config,
     replay_protector: Arc<ReplayProtector>,
     producer_sequences: Arc<DashMap<String, u64>>,
-    expected_sequences: Arc<DashMap<String, u64>>,
 }
 
 impl OrderingManager {
     pub fn new(config: &Config) -> Result<Self, Box<dyn std::error::Error>> {
-        Ok(Self {
+        Ok(Self::with_replay_state(config, Arc::new(LocalReplayState::new())))
+    }
+
+    /// Sequence state in `state` (shared by all instances with the postgres backend).
+    pub fn with_replay_state(config: &Config, state: Arc<dyn ReplayStateBackend>) -> Self {
+        Self {
             config: config.clone(),
-            replay_protector: Arc::new(ReplayProtector::new()?),
+            replay_protector: Arc::new(ReplayProtector::with_state(state, ORDERING_REPLAY_SCOPE)),
             producer_sequences: Arc::new(DashMap::new()),
-            expected_sequences: Arc::new(DashMap::new()),
-        })
+        }
     }
     
     pub async fn check_ordering(
@@ -43,26 +50,19 @@ impl OrderingManager {
         envelope: &EventEnvelope,
     ) -> Result<bool, Box<dyn std::error::Error>> {
         // Check replay protection
-        self.replay_protector.check_replay(producer_id, &envelope.nonce, &envelope.timestamp, envelope.sequence_number).await?;
+        let previous = self.replay_protector
+            .check_replay_sequence(producer_id, &envelope.nonce, &envelope.timestamp, envelope.sequence_number)
+            .await?;
         
-        // Check sequence ordering
+        // Check sequence ordering: strictly after the previously accepted sequence
         let sequence = envelope.sequence_number;
-        {
-            let mut expected = self.expected_sequences
-                .entry(producer_id.to_string())
-                .or_insert(0);
-            
-            // Check if sequence is in order
-            if sequence < *expected {
+        if let Some(previous) = previous {
+            if sequence <= previous {
                 // Out of order or replay
-                let exp_copy = *expected;
                 warn!("Out of order sequence for producer {}: expected {}, got {}", 
-                      producer_id, exp_copy, sequence);
+                      producer_id, previous + 1, sequence);
                 return Ok(false);
             }
-            
-            // Update expected sequence
-            *expected = sequence + 1;
         }
         
         // Record sequence
@@ -0,0 +1,263 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/outbox.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Transactional outbox relay - publishes ingest_outbox messages written in the telemetry unit of work to the downstream consumer after commit, at least once
+
+/*
+ * Transactional Outbox
+ *
+ * Accepted telemetry is announced downstream without a dual write: the handler inserts an
+ * ingest_outbox row (topic telemetry.accepted) in the same StorageTx as raw_events, telemetry and
+ * audit, so the message exists if and only if the event committed. The relay then:
+ *
+ *   1. claims due pending rows oldest first, leasing them (next_attempt_at = now + lease)
+ *   2. publishes each as one JSON line {outbox_id, topic, aggregate_id, created_at, payload}
+ *   3. marks it published; a failed publish is retried with exponential backoff (capped)
+ *
+ * A crash between publish and mark leaves the row pending: it is published again when its lease
+ * expires, so delivery is at-least-once and consumers dedupe on outbox_id. Published rows are
+ * purged after RANSOMEYE_INGEST_OUTBOX_RETAIN_SECS.
+ *
+ * The outbox is enabled by RANSOMEYE_INGEST_OUTBOX_PUBLISH_ADDR (host:port of the consumer's
+ * newline-delimited JSON listener). Without it no rows are written.
+ */
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::Value as JsonValue;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tracing::{error, info, warn};
+
+use crate::storage::{OutboxEntry, OutboxRecord, TelemetryStore};
+use crate::topology::ZoneLabel;
+
+pub const TOPIC_TELEMETRY_ACCEPTED: &str = "telemetry.accepted";
+
+const BACKOFF_BASE: Duration = Duration::from_secs(1);
+const BACKOFF_MAX: Duration = Duration::from_secs(300);
+/// Published rows are purged at most this often
+const PURGE_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone)]
+pub struct OutboxConfig {
+    /// None = outbox disabled (no rows written, no relay)
+    pub publish_addr: Option<String>,
+    pub poll_interval: Duration,
+    pub batch_size: i64,
+    pub publish_timeout: Duration,
+    pub retain: Duration,
+}
+
+fn env_u64(key: &str, default_value: u64) -> Result<u64, String> {
+    match std::env::var(key) {
+        Ok(v) => v
+            .parse::<u64>()
+            .ok()
+            .filter(|n| *n > 0)
+            .ok_or_else(|| format!("Invalid {} '{}' (expected integer > 0)", key, v)),
+        Err(_) => Ok(default_value),
+    }
+}
+
+impl OutboxConfig {
+    pub fn from_env() -> Result<Self, String> {
+        let publish_addr = std::env::var("RANSOMEYE_INGEST_OUTBOX_PUBLISH_ADDR").ok().filter(|v| !v.trim().is_empty());
+        let cfg = Self {
+            publish_addr,
+            poll_interval: Duration::from_millis(env_u64("RANSOMEYE_INGEST_OUTBOX_POLL_MS", 500)?),
+            batch_size: env_u64("RANSOMEYE_INGEST_OUTBOX_BATCH_SIZE", 100)?.min(10_000) as i64,
+            publish_timeout: Duration::from_secs(env_u64("RANSOMEYE_INGEST_OUTBOX_PUBLISH_TIMEOUT_SECS", 10)?),
+            retain: Duration::from_secs(env_u64("RANSOMEYE_INGEST_OUTBOX_RETAIN_SECS", 86_400)?),
+        };
+        match &cfg.publish_addr {
+            Some(addr) => info!("Ingest outbox enabled | publish_addr={} | batch_size={}", addr, cfg.batch_size),
+            None => info!("Ingest outbox disabled (no RANSOMEYE_INGEST_OUTBOX_PUBLISH_ADDR)"),
+        }
+        Ok(cfg)
+    }
+
+    pub fn disabled() -> Self {
+        Self {
+            publish_addr: None,
+            poll_interval: Duration::from_millis(500),
+            batch_size: 100,
+            publish_timeout: Duration::from_secs(10),
+            retain: Duration::from_secs(86_400),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.publish_addr.is_some()
+    }
+
+    /// Claim lease: long enough for a whole batch to publish before anyone re-claims it.
+    pub fn lease(&self) -> Duration {
+        self.publish_timeout + Duration::from_secs(30)
+    }
+}
+
+/// telemetry.accepted message for a committed event
+#[allow(clippy::too_many_arguments)]
+pub fn telemetry_accepted(
+    raw_event_id: uuid::Uuid,
+    source: &str,
+    agent_id: uuid::Uuid,
+    message_id: &str,
+    event_name: &str,
+    observed_at: chrono::DateTime<Utc>,
+    residency_region: Option<&str>,
+    zone: Option<&ZoneLabel>,
+) -> OutboxRecord {
+    OutboxRecord {
+        topic: TOPIC_TELEMETRY_ACCEPTED.to_string(),
+        aggregate_id: raw_event_id,
+        payload: serde_json::json!({
+            "raw_event_id": raw_event_id.to_string(),
+            "source": source,
+            "agent_id": agent_id.to_string(),
+            "message_id": message_id,
+            "event_name": event_name,
+            "observed_at": observed_at.to_rfc3339(),
+            "residency_region": residency_region,
+            "site": zone.map(|z| z.site.as_str()),
+            "zone": zone.map(|z| z.zone.as_str()),
+        }),
+    }
+}
+
+/// Delay before the next attempt after `attempts` failed ones (1s doubling, capped at 5 min)
+pub fn backoff(attempts: i32) -> Duration {
+    let exp = attempts.clamp(0, 16) as u32;
+    BACKOFF_BASE.saturating_mul(1u32 << exp).min(BACKOFF_MAX)
+}
+
+/// Wire form of one message
+pub fn message(entry: &OutboxEntry) -> JsonValue {
+    serde_json::json!({
+        "outbox_id": entry.outbox_id.to_string(),
+        "topic": entry.topic,
+        "aggregate_id": entry.aggregate_id.to_string(),
+        "created_at": entry.created_at.to_rfc3339(),
+        "payload": entry.payload,
+    })
+}
+
+#[async_trait]
+pub trait OutboxPublisher: Send + Sync {
+    async fn publish(&self, entries: &[OutboxEntry]) -> Vec<Result<(), String>>;
+}
+
+/// Newline-delimited JSON over TCP; one connection per batch.
+pub struct TcpPublisher {
+    addr: String,
+    timeout: Duration,
+}
+
+impl TcpPublisher {
+    pub fn new(addr: String, timeout: Duration) -> Self {
+        Self { addr, timeout }
+    }
+
+    async fn send(&self, entries: &[OutboxEntry]) -> Result<(), String> {
+        let mut stream = TcpStream::connect(&self.addr).await.map_err(|e| format!("connect {}: {}", self.addr, e))?;
+        let mut buf = Vec::new();
+        for entry in entries {
+            serde_json::to_writer(&mut buf, &message(entry)).map_err(|e| format!("serialize: {}", e))?;
+            buf.push(b'\n');
+        }
+        stream.write_all(&buf).await.map_err(|e| format!("write: {}", e))?;
+        stream.flush().await.map_err(|e| format!("flush: {}", e))?;
+        stream.shutdown().await.map_err(|e| format!("shutdown: {}", e))
+    }
+}
+
+#[async_trait]
+impl OutboxPublisher for TcpPublisher {
+    async fn publish(&self, entries: &[OutboxEntry]) -> Vec<Result<(), String>> {
+        let outcome = match tokio::time::timeout(self.timeout, self.send(entries)).await {
+            Ok(result) => result,
+            Err(_) => Err(format!("publish timed out after {:?}", self.timeout)),
+        };
+        vec![outcome; entries.len()]
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RelayPass {
+    pub published: usize,
+    pub failed: usize,
+    pub purged: u64,
+}
+
+pub struct OutboxRelay {
+    cfg: OutboxConfig,
+    store: Arc<dyn TelemetryStore>,
+    publisher: Arc<dyn OutboxPublisher>,
+    last_purge: parking_lot::Mutex<Option<Instant>>,
+}
+
+impl OutboxRelay {
+    /// `store` should not share a Postgres connection with request handlers: their explicit
+    /// BEGIN/COMMIT would otherwise enclose the relay's statements.
+    pub fn new(cfg: OutboxConfig, store: Arc<dyn TelemetryStore>, publisher: Arc<dyn OutboxPublisher>) -> Self {
+        Self { cfg, store, publisher, last_purge: parking_lot::Mutex::new(None) }
+    }
+
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut tick = tokio::time::interval(self.cfg.poll_interval);
+            loop {
+                tick.tick().await;
+                match self.run_once().await {
+                    Ok(pass) if pass.failed > 0 => {
+                        warn!("Outbox relay: published={} failed={} (retried with backoff)", pass.published, pass.failed)
+                    }
+                    Ok(_) => {}
+                    Err(e) => error!("Outbox relay pass failed: {}", e),
+                }
+            }
+        })
+    }
+
+    /// One claim/publish/mark pass plus purge of old published rows.
+    pub async fn run_once(&self) -> Result<RelayPass, String> {
+        let mut pass = RelayPass::default();
+        let entries = self
+            .store
+            .claim_outbox(self.cfg.batch_size, self.cfg.lease())
+            .await
+            .map_err(|e| e.to_string())?;
+        if !entries.is_empty() {
+            let outcomes = self.publisher.publish(&entries).await;
+            for (entry, outcome) in entries.iter().zip(outcomes) {
+                match outcome {
+                    Ok(()) => {
+                        // If this fails the lease expires and the message is published again
+                        self.store.complete_outbox(entry.outbox_id).await.map_err(|e| e.to_string())?;
+                        pass.published += 1;
+                    }
+                    Err(e) => {
+                        self.store
+                            .retry_outbox(entry.outbox_id, &e, backoff(entry.attempts))
+                            .await
+                            .map_err(|e| e.to_string())?;
+                        pass.failed += 1;
+                    }
+                }
+            }
+        }
+        let purge_due = match *self.last_purge.lock() {
+            Some(at) => at.elapsed() >= PURGE_INTERVAL,
+            None => true,
+        };
+        if purge_due {
+            let cutoff = Utc::now() - chrono::Duration::from_std(self.cfg.retain).map_err(|e| e.to_string())?;
+            pass.purged = self.store.purge_outbox(cutoff).await.map_err(|e| e.to_string())?;
+            *self.last_purge.lock() = Some(Instant::now());
+        }
+        Ok(pass)
+    }
+}
@@ -0,0 +1,155 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/payload_policy.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: raw_events payload storage policy - full envelopes for interesting events, hashes plus extracted fields for routine ones
+
+/*
+ * Raw Payload Storage Policy
+ *
+ * RANSOMEYE_RAW_PAYLOAD_POLICY=full (default) keeps today's behaviour: every raw_events row
+ * stores the envelope as received. In `sampled` mode the full payload is kept only for:
+ *   - events whose category is in RANSOMEYE_RAW_PAYLOAD_FULL_CATEGORIES
+ *   - events whose agent sub-type is in RANSOMEYE_RAW_PAYLOAD_FULL_EVENT_TYPES
+ *   - a content-defined sample of everything else (RANSOMEYE_RAW_PAYLOAD_SAMPLE_RATE)
+ * Other rows store a summary document instead. payload_sha256 always covers the received
+ * envelope, so a summary row still proves what was received; it cannot be replayed.
+ *
+ * Sampling is keyed on the envelope SHA-256, not a random draw: the same envelope always gets
+ * the same decision (re-delivery, replay, multiple ingest instances).
+ */
+
+use serde_json::value::RawValue;
+use serde_json::Value as JsonValue;
+
+use crate::protocol::signed_event::EnvelopeRef;
+use crate::storage::{PayloadStorage, TelemetrySource};
+
+const PPM: u64 = 1_000_000;
+
+const DEFAULT_FULL_CATEGORIES: &str = "detection,canary";
+const DEFAULT_FULL_EVENT_TYPES: &str = "MassWrite";
+const DEFAULT_SAMPLE_RATE: f64 = 0.01;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadPolicyMode {
+    /// Store every envelope in full
+    Full,
+    /// Full for interesting events and a content-defined sample, summary otherwise
+    Sampled,
+}
+
+#[derive(Debug, Clone)]
+pub struct PayloadStoragePolicy {
+    pub mode: PayloadPolicyMode,
+    pub full_categories: Vec<String>,
+    pub full_event_types: Vec<String>,
+    /// Fraction of routine events stored in full (0.0..=1.0)
+    pub sample_rate: f64,
+}
+
+/// Event facts the policy decides on (already extracted by the ingest handler).
+#[derive(Debug, Clone, Copy)]
+pub struct PayloadFacts<'a> {
+    pub source: TelemetrySource,
+    pub event_category: Option<&'a str>,
+    pub event_type: Option<&'a str>,
+}
+
+/// Per-row decision, persisted as raw_events.payload_storage / payload_storage_reason.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PayloadDecision {
+    pub storage: PayloadStorage,
+    pub reason: String,
+}
+
+impl PayloadStoragePolicy {
+    /// Store everything in full (default, and the behaviour before the policy existed).
+    pub fn full() -> Self {
+        Self {
+            mode: PayloadPolicyMode::Full,
+            full_categories: split_list(DEFAULT_FULL_CATEGORIES),
+            full_event_types: split_list(DEFAULT_FULL_EVENT_TYPES),
+            sample_rate: DEFAULT_SAMPLE_RATE,
+        }
+    }
+
+    pub fn from_env() -> Result<Self, String> {
+        let mode = match std::env::var("RANSOMEYE_RAW_PAYLOAD_POLICY").as_deref() {
+            Ok("full") | Err(_) => PayloadPolicyMode::Full,
+            Ok("sampled") => PayloadPolicyMode::Sampled,
+            Ok(other) => {
+                return Err(format!("Invalid RANSOMEYE_RAW_PAYLOAD_POLICY '{}' (expected full|sampled)", other));
+            }
+        };
+        let full_categories = split_list(
+            &std::env::var("RANSOMEYE_RAW_PAYLOAD_FULL_CATEGORIES").unwrap_or_else(|_| DEFAULT_FULL_CATEGORIES.to_string()),
+        );
+        let full_event_types = split_list(
+            &std::env::var("RANSOMEYE_RAW_PAYLOAD_FULL_EVENT_TYPES").unwrap_or_else(|_| DEFAULT_FULL_EVENT_TYPES.to_string()),
+        );
+        let sample_rate = match std::env::var("RANSOMEYE_RAW_PAYLOAD_SAMPLE_RATE") {
+            Ok(v) => v
+                .parse::<f64>()
+                .map_err(|e| format!("Invalid RANSOMEYE_RAW_PAYLOAD_SAMPLE_RATE='{}': {}", v, e))?,
+            Err(_) => DEFAULT_SAMPLE_RATE,
+        };
+        if !(0.0..=1.0).contains(&sample_rate) {
+            return Err(format!("RANSOMEYE_RAW_PAYLOAD_SAMPLE_RATE must be in 0.0..=1.0 (got {})", sample_rate));
+        }
+        Ok(Self { mode, full_categories, full_event_types, sample_rate })
+    }
+
+    pub fn decide(&self, facts: PayloadFacts<'_>, envelope_sha256: &[u8]) -> PayloadDecision {
+        let full = |reason: String| PayloadDecision { storage: PayloadStorage::Full, reason };
+        if self.mode == PayloadPolicyMode::Full {
+            return full("policy:full".to_string());
+        }
+        if let Some(category) = facts.event_category.filter(|c| self.full_categories.iter().any(|f| f == c)) {
+            return full(format!("category:{}", category));
+        }
+        if let Some(event_type) = facts.event_type.filter(|t| self.full_event_types.iter().any(|f| f == t)) {
+            return full(format!("event_type:{}", event_type));
+        }
+        if sample_bucket(envelope_sha256) < (self.sample_rate * PPM as f64).round() as u64 {
+            return full("sampled".to_string());
+        }
+        PayloadDecision { storage: PayloadStorage::Summary, reason: "routine".to_string() }
+    }
+}
+
+/// Summary document stored in raw_events.payload_json for `PayloadStorage::Summary` rows.
+/// `fields` carries the handler's extracted values; `data_sha256` lets the row be matched
+/// against the typed telemetry row that stores envelope.data.
+pub fn summary_payload(
+    decision: &PayloadDecision,
+    facts: PayloadFacts<'_>,
+    envelope: &EnvelopeRef<'_>,
+    envelope_sha256: &[u8],
+    data_sha256: &[u8],
+    fields: JsonValue,
+) -> Result<Box<RawValue>, serde_json::Error> {
+    serde_json::value::to_raw_value(&serde_json::json!({
+        "payload_storage": PayloadStorage::Summary.as_str(),
+        "reason": decision.reason,
+        "source": facts.source.as_str(),
+        "event_id": envelope.event_id,
+        "timestamp": envelope.timestamp,
+        "component_id": envelope.component_id,
+        "event_category": facts.event_category,
+        "event_type": facts.event_type,
+        "envelope_sha256": hex::encode(envelope_sha256),
+        "data_sha256": hex::encode(data_sha256),
+        "fields": fields,
+    }))
+}
+
+/// Position of an envelope in [0, PPM), from the first 8 bytes of its SHA-256.
+fn sample_bucket(envelope_sha256: &[u8]) -> u64 {
+    let mut prefix = [0u8; 8];
+    let n = envelope_sha256.len().min(8);
+    prefix[..n].copy_from_slice(&envelope_sha256[..n]);
+    u64::from_be_bytes(prefix) % PPM
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).collect()
+}
@@ -0,0 +1,307 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/pcap_capture.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Triggered packet capture - queues a bounded, host-filtered DPI probe capture for critical detections in the detection transaction, expires stale requests, validates uploaded pcaps and spools them with a manifest for the evidence store
+
+/*
+ * Triggered Packet Capture
+ *
+ * A critical detection that is not suppressed asks the DPI probes for a packet capture of the
+ * host it concerns. In the detection's transaction a pcap_captures row is queued (status
+ * requested) with the host address, a BPF filter for it and the configured bounds:
+ *
+ *   host address   artifacts.host_ip, else the latest local address reported by the agent in
+ *                  artifacts.agent_id (linux_agent_telemetry.network_dst_ip)
+ *   incident       artifacts.incident_id (optional)
+ *   bounds         RANSOMEYE_INGEST_PCAP_DURATION_SECS, _MAX_BYTES and _SNAPLEN
+ *
+ * A detection without a host address queues nothing. A host is captured once at a time: while
+ * a request for it is pending or being captured, later detections for it queue nothing.
+ *
+ * Probes take requests over the control channel (agent bearer token, dpi_probe agents only):
+ * POST /probes/captures/claim hands the oldest request to the first probe that asks, the probe
+ * captures for duration_secs or until max_bytes and uploads the file
+ * (POST /probes/captures/upload) or reports why it could not (POST /probes/captures/fail).
+ * Ingest checks the size, SHA-256 and pcap header of an upload and writes the pcap with its
+ * manifest to the spool directory; `reporting import-captures` seals it into the evidence store,
+ * linked to the detection and incident. Requests not claimed within CLAIM_WINDOW_SECS, and
+ * captures not uploaded UPLOAD_GRACE_SECS after their end, expire.
+ *
+ * Off unless RANSOMEYE_INGEST_PCAP_CAPTURE is set; needs the Postgres control plane.
+ */
+
+use std::fs;
+use std::io::Write;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use tokio_postgres::Client;
+use tracing::info;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::storage::DetectionRecord;
+
+/// Severity that triggers a capture
+pub const TRIGGER_SEVERITY: &str = "critical";
+/// A request no probe claimed within this window expires (the traffic of interest has passed)
+pub const CLAIM_WINDOW_SECS: i64 = 600;
+/// Time after the end of a capture for the probe to upload it
+pub const UPLOAD_GRACE_SECS: i64 = 900;
+
+const DEFAULT_DURATION_SECS: u64 = 300;
+const MAX_DURATION_SECS: u64 = 3600;
+const DEFAULT_MAX_BYTES: u64 = 64 * 1024 * 1024;
+const MAX_MAX_BYTES: u64 = 1024 * 1024 * 1024;
+const DEFAULT_SNAPLEN: u64 = 65535;
+const MAX_SNAPLEN: u64 = 262_144;
+const DEFAULT_SPOOL_DIR: &str = "/var/lib/ransomeye/ingest/pcap-spool";
+
+/// pcap global header length
+pub const PCAP_HEADER_LEN: usize = 24;
+/// Classic pcap magic numbers (microsecond and nanosecond timestamps, either byte order)
+const PCAP_MAGICS: [[u8; 4]; 4] = [
+    [0xd4, 0xc3, 0xb2, 0xa1],
+    [0xa1, 0xb2, 0xc3, 0xd4],
+    [0x4d, 0x3c, 0xb2, 0xa1],
+    [0xa1, 0xb2, 0x3c, 0x4d],
+];
+
+fn env_bounded(key: &str, default_value: u64, min: u64, max: u64) -> Result<u64, String> {
+    match std::env::var(key) {
+        Ok(v) => v
+            .parse::<u64>()
+            .ok()
+            .filter(|n| (min..=max).contains(n))
+            .ok_or_else(|| format!("Invalid {} '{}' (expected {}..={})", key, v, min, max)),
+        Err(_) => Ok(default_value),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PcapCaptureConfig {
+    pub duration_secs: u32,
+    pub max_bytes: u64,
+    pub snaplen: u32,
+    /// Uploaded pcaps and their manifests, until `reporting import-captures` takes them
+    pub spool_dir: PathBuf,
+}
+
+impl PcapCaptureConfig {
+    /// RANSOMEYE_INGEST_PCAP_CAPTURE=true|1 enables triggered captures (default off);
+    /// RANSOMEYE_INGEST_PCAP_DURATION_SECS (default 300, at most 3600),
+    /// RANSOMEYE_INGEST_PCAP_MAX_BYTES (default 64 MiB, at most 1 GiB),
+    /// RANSOMEYE_INGEST_PCAP_SNAPLEN (default 65535),
+    /// RANSOMEYE_INGEST_PCAP_SPOOL_DIR (default /var/lib/ransomeye/ingest/pcap-spool).
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let enabled = std::env::var("RANSOMEYE_INGEST_PCAP_CAPTURE")
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+        if !enabled {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            duration_secs: env_bounded("RANSOMEYE_INGEST_PCAP_DURATION_SECS", DEFAULT_DURATION_SECS, 10, MAX_DURATION_SECS)? as u32,
+            max_bytes: env_bounded("RANSOMEYE_INGEST_PCAP_MAX_BYTES", DEFAULT_MAX_BYTES, 1024 * 1024, MAX_MAX_BYTES)?,
+            snaplen: env_bounded("RANSOMEYE_INGEST_PCAP_SNAPLEN", DEFAULT_SNAPLEN, 64, MAX_SNAPLEN)? as u32,
+            spool_dir: PathBuf::from(
+                std::env::var("RANSOMEYE_INGEST_PCAP_SPOOL_DIR").unwrap_or_else(|_| DEFAULT_SPOOL_DIR.to_string()),
+            ),
+        }))
+    }
+}
+
+/// Host address named by the detection (artifacts.host_ip).
+pub fn host_address(artifacts: &JsonValue) -> Option<IpAddr> {
+    artifacts.get("host_ip")?.as_str()?.trim().parse().ok()
+}
+
+/// Incident the detection belongs to (artifacts.incident_id).
+pub fn incident_id(artifacts: &JsonValue) -> Option<Uuid> {
+    Uuid::parse_str(artifacts.get("incident_id")?.as_str()?.trim()).ok()
+}
+
+/// BPF filter for all traffic of one host. Built only from a parsed address, so a detection
+/// artifact can never inject filter syntax.
+pub fn bpf_filter(host: IpAddr) -> String {
+    format!("host {}", host)
+}
+
+/// Uploaded file: a classic pcap (global header with a known magic) of at most `max_bytes`.
+pub fn check_pcap(data: &[u8], max_bytes: u64) -> Result<(), String> {
+    if data.len() as u64 > max_bytes {
+        return Err(format!("pcap of {} bytes exceeds the capture bound of {} bytes", data.len(), max_bytes));
+    }
+    if data.len() < PCAP_HEADER_LEN {
+        return Err(format!("pcap of {} bytes is shorter than the pcap header", data.len()));
+    }
+    if !PCAP_MAGICS.iter().any(|m| data[..4] == m[..]) {
+        return Err(format!("not a pcap file (magic {})", hex::encode(&data[..4])));
+    }
+    Ok(())
+}
+
+/// Capture request handed to a probe (POST /probes/captures/claim).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct CaptureOrder {
+    pub capture_id: Uuid,
+    /// libpcap filter expression ("host <address>")
+    pub bpf_filter: String,
+    pub duration_secs: i32,
+    /// Stop before the file would exceed this size
+    pub max_bytes: i64,
+    pub snaplen: i32,
+}
+
+/// pcap_captures.status
+pub const CAPTURE_STATUSES: &[&str] = &["requested", "capturing", "uploaded", "failed", "expired"];
+
+/// A capture request and its outcome, as listed by the admin API.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PcapCapture {
+    pub capture_id: Uuid,
+    /// Triggering detection (None once retention purged it)
+    pub detection_id: Option<Uuid>,
+    pub incident_id: Option<Uuid>,
+    pub host_ip: String,
+    pub bpf_filter: String,
+    pub duration_secs: i32,
+    pub max_bytes: i64,
+    pub snaplen: i32,
+    /// requested, capturing, uploaded, failed or expired
+    pub status: String,
+    pub requested_at: DateTime<Utc>,
+    pub probe_agent_id: Option<Uuid>,
+    pub claimed_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub pcap_sha256: Option<String>,
+    pub pcap_bytes: Option<i64>,
+    pub failure_reason: Option<String>,
+}
+
+/// Sidecar of a spooled pcap (<capture_id>.json next to <capture_id>.pcap); read by the
+/// reporting importer.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpoolManifest {
+    pub capture_id: Uuid,
+    pub detection_id: Option<Uuid>,
+    pub incident_id: Option<Uuid>,
+    pub host_ip: String,
+    pub bpf_filter: String,
+    pub probe_agent_id: Uuid,
+    pub probe_component_id: String,
+    pub requested_at: DateTime<Utc>,
+    pub claimed_at: DateTime<Utc>,
+    pub uploaded_at: DateTime<Utc>,
+    pub pcap_file: String,
+    pub pcap_sha256: String,
+    pub pcap_bytes: u64,
+}
+
+fn write_atomic(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    let mut file = fs::File::create(&tmp)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)
+}
+
+/// Write the pcap, then its manifest: the importer only takes captures whose manifest exists.
+/// A retried upload of the same capture replaces both.
+pub fn write_spool(spool_dir: &Path, manifest: &SpoolManifest, pcap: &[u8]) -> std::io::Result<()> {
+    fs::create_dir_all(spool_dir)?;
+    write_atomic(&spool_dir.join(&manifest.pcap_file), pcap)?;
+    let json = serde_json::to_vec_pretty(manifest).map_err(std::io::Error::other)?;
+    write_atomic(&spool_dir.join(format!("{}.json", manifest.capture_id)), &json)
+}
+
+/// Remove a spooled capture whose upload was not recorded.
+pub fn remove_spool(spool_dir: &Path, capture_id: Uuid) {
+    for name in [format!("{}.json", capture_id), format!("{}.pcap", capture_id)] {
+        let _ = fs::remove_file(spool_dir.join(name));
+    }
+}
+
+/// Expire requests nobody claimed in time and captures never uploaded; returns rows expired.
+pub async fn expire_stale(db: &Client) -> Result<u64, tokio_postgres::Error> {
+    db.execute(
+        r#"
+        UPDATE pcap_captures
+        SET status = 'expired', finished_at = now()
+        WHERE (status = 'requested' AND requested_at < now() - make_interval(secs => $1))
+           OR (status = 'capturing' AND claimed_at < now() - make_interval(secs => duration_secs + $2))
+        "#,
+        &[&(CLAIM_WINDOW_SECS as f64), &(UPLOAD_GRACE_SECS as f64)],
+    )
+    .await
+}
+
+/// Queue a capture of the detection's host (detection transaction). Returns the capture id, or
+/// None when the detection is not critical, names no host or its host is already captured.
+pub async fn queue(
+    db: &Client,
+    config: &PcapCaptureConfig,
+    detection_id: Uuid,
+    detection: &DetectionRecord,
+) -> Result<Option<Uuid>, tokio_postgres::Error> {
+    if detection.severity != TRIGGER_SEVERITY {
+        return Ok(None);
+    }
+    let host = match host_address(&detection.artifacts) {
+        Some(host) => Some(host),
+        None => match detection.artifacts.get("agent_id").and_then(JsonValue::as_str).and_then(|a| Uuid::parse_str(a).ok()) {
+            Some(agent_id) => db
+                .query_opt(
+                    r#"
+                    SELECT network_dst_ip FROM linux_agent_telemetry
+                    WHERE agent_id = $1 AND network_dst_ip IS NOT NULL
+                    ORDER BY observed_at DESC
+                    LIMIT 1
+                    "#,
+                    &[&agent_id],
+                )
+                .await?
+                .map(|r| r.get::<_, IpAddr>(0)),
+            None => None,
+        },
+    };
+    let Some(host) = host else {
+        info!("No packet capture for detection {}: no host address in its artifacts", detection_id);
+        return Ok(None);
+    };
+
+    expire_stale(db).await?;
+    let capture_id = Uuid::new_v4();
+    let queued = db
+        .execute(
+            r#"
+            INSERT INTO pcap_captures (
+                capture_id, detection_id, incident_id, host_ip, bpf_filter, duration_secs, max_bytes, snaplen
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (host_ip) WHERE status IN ('requested', 'capturing') DO NOTHING
+            "#,
+            &[
+                &capture_id,
+                &detection_id,
+                &incident_id(&detection.artifacts),
+                &host,
+                &bpf_filter(host),
+                &(config.duration_secs as i32),
+                &(config.max_bytes as i64),
+                &(config.snaplen as i32),
+            ],
+        )
+        .await?;
+    if queued == 0 {
+        info!("No packet capture for detection {}: host {} is already being captured", detection_id, host);
+        return Ok(None);
+    }
+    info!(
+        "Packet capture requested | capture_id={} | detection_id={} | host={} | duration_secs={}",
+        capture_id, detection_id, host, config.duration_secs
+    );
+    Ok(Some(capture_id))
+}
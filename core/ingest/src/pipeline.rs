@@ -0,0 +1,443 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/pipeline.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Multi-core ingest pipeline - post-verification persistence sharded by agent id across worker tasks, each with its own DB connection, processing one agent's events in arrival order
+
+/*
+ * Sharded Ingest Pipeline
+ *
+ * Request handlers do the CPU-bound part (parse, token binding, signature, policy checks) in
+ * parallel. The unit of work that persists an accepted event (audit, raw_events, telemetry,
+ * outbox) is handed to one of RANSOMEYE_INGEST_PIPELINE_SHARDS worker tasks, chosen by agent id:
+ *
+ *   - every event of an agent goes to the same shard, and a shard runs its queue one unit of work
+ *     at a time, so an agent's events commit in the order ingest accepted them;
+ *   - each shard has its own Postgres connection, so shards commit in parallel instead of
+ *     queueing behind one client. Units of work that extend the audit chain wait for each other
+ *     only on its transaction-scoped advisory lock (storage/postgres.rs), held until COMMIT.
+ *
+ * A full shard queue (RANSOMEYE_INGEST_PIPELINE_QUEUE_DEPTH) refuses the request with 503 rather
+ * than growing without bound; the agent retries. A unit of work that panics fails only its own
+ * request (500); the shard keeps running.
+ *
+ * After an outage agents replay hours of spooled events, which would queue ahead of every fresh
+ * event on their shards. Each shard queue therefore has two lanes: events observed more than
+ * RANSOMEYE_INGEST_BACKLOG_AGE_SECS ago wait in the backlog lane, the rest in the realtime lane.
+ * While both hold work the shard runs RANSOMEYE_INGEST_REALTIME_RATIO realtime units of work per
+ * backlog unit, so current activity is stored within seconds and the backlog still drains. All
+ * queued units of an agent stay in one lane (the lane of its oldest queued unit), so an agent's
+ * events still commit in the order they were accepted.
+ *
+ * Every shard keeps a moving average of how long its units of work take (mostly database time),
+ * and the pipeline one over all shards; load_shedding.rs reads them with the queue depths.
+ */
+
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::Serialize;
+use tokio::sync::{oneshot, Notify};
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::storage::TelemetryStore;
+
+/// Upper bound on shards (each holds a DB connection)
+pub const MAX_PIPELINE_SHARDS: usize = 64;
+const DEFAULT_QUEUE_DEPTH: usize = 1024;
+const DEFAULT_BACKLOG_AGE_SECS: usize = 300;
+const DEFAULT_REALTIME_RATIO: usize = 4;
+
+type Job = Box<dyn FnOnce(Arc<dyn TelemetryStore>) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PipelineConfig {
+    pub shards: usize,
+    /// Units of work waiting per shard before requests are refused
+    pub queue_depth: usize,
+    /// Events observed longer ago than this wait in the backlog lane
+    pub backlog_age: Duration,
+    /// Realtime units of work run per backlog unit while both lanes hold work
+    pub realtime_ratio: usize,
+}
+
+fn env_usize(key: &str, default_value: usize, max: usize) -> Result<usize, String> {
+    match std::env::var(key) {
+        Ok(v) => v
+            .parse::<usize>()
+            .ok()
+            .filter(|n| (1..=max).contains(n))
+            .ok_or_else(|| format!("Invalid {} '{}' (expected integer in 1..={})", key, v, max)),
+        Err(_) => Ok(default_value),
+    }
+}
+
+impl PipelineConfig {
+    /// Shards default to the number of cores (at most MAX_PIPELINE_SHARDS).
+    pub fn from_env() -> Result<Self, String> {
+        let cores = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        Ok(Self {
+            shards: env_usize("RANSOMEYE_INGEST_PIPELINE_SHARDS", cores.min(MAX_PIPELINE_SHARDS), MAX_PIPELINE_SHARDS)?,
+            queue_depth: env_usize("RANSOMEYE_INGEST_PIPELINE_QUEUE_DEPTH", DEFAULT_QUEUE_DEPTH, 1 << 20)?,
+            backlog_age: Duration::from_secs(
+                env_usize("RANSOMEYE_INGEST_BACKLOG_AGE_SECS", DEFAULT_BACKLOG_AGE_SECS, 7 * 86_400)? as u64,
+            ),
+            realtime_ratio: env_usize("RANSOMEYE_INGEST_REALTIME_RATIO", DEFAULT_REALTIME_RATIO, 1000)?,
+        })
+    }
+}
+
+/// Shard of an agent; stable for the life of the process.
+pub fn shard_for(agent_id: Uuid, shards: usize) -> usize {
+    let v = agent_id.as_u128();
+    // Fold both halves: v4 ids are random throughout, v7 ids share their leading timestamp bits
+    (((v >> 64) as u64 ^ v as u64) % shards.max(1) as u64) as usize
+}
+
+/// Queue and latency seen by one agent's events (see load_shedding.rs).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PipelineLoad {
+    /// Units of work waiting on the agent's shard
+    pub shard_queued: usize,
+    /// Units of work waiting on all shards
+    pub queued: usize,
+    /// Per-shard queue bound
+    pub queue_depth: usize,
+    pub shards: usize,
+    /// Moving average duration of a unit of work, all shards
+    pub latency: Duration,
+    /// Moving average duration of a unit of work on the agent's shard
+    pub shard_latency: Duration,
+}
+
+impl PipelineLoad {
+    /// Fill of the agent's shard queue, 0-100
+    pub fn shard_fill_pct(&self) -> u64 {
+        fill_pct(self.shard_queued, self.queue_depth)
+    }
+
+    /// Fill of all shard queues together, 0-100
+    pub fn fill_pct(&self) -> u64 {
+        fill_pct(self.queued, self.queue_depth * self.shards)
+    }
+}
+
+fn fill_pct(queued: usize, capacity: usize) -> u64 {
+    (queued as u64 * 100 / capacity.max(1) as u64).min(100)
+}
+
+/// Fold a sample into a moving average (weight 1/8, as TCP smooths round-trip times).
+fn observe_latency(average_us: &AtomicU64, sample: Duration) {
+    let sample = sample.as_micros().min(u64::MAX as u128) as u64;
+    let _ = average_us.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |avg| {
+        Some(if avg == 0 { sample.max(1) } else { (avg - avg / 8 + sample / 8).max(1) })
+    });
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PipelineError {
+    /// The shard's queue is full (503)
+    Busy,
+    /// Workers not started, stopped, or the unit of work panicked (500)
+    Unavailable,
+}
+
+/// Lane of a shard queue a unit of work waits in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lane {
+    Realtime,
+    /// Events observed longer ago than the backlog age (replays after an outage)
+    Backlog,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ShardStats {
+    pub shard: usize,
+    /// Units of work waiting
+    pub queued: usize,
+    /// Of `queued`, units of work in the backlog lane
+    pub backlog_queued: usize,
+    pub processed: u64,
+    /// Of `processed`, units of work run from the backlog lane
+    pub backlog_processed: u64,
+    /// Refused with 503 because the queue was full
+    pub refused: u64,
+    /// Moving average duration of a unit of work, microseconds (0: none yet)
+    pub latency_us: u64,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PipelineStats {
+    pub shards: usize,
+    pub queue_depth: usize,
+    /// Events observed longer ago than this go to the backlog lane
+    pub backlog_age_secs: u64,
+    /// Realtime units of work run per backlog unit while both lanes hold work
+    pub realtime_ratio: usize,
+    pub started: bool,
+    /// Moving average duration of a unit of work over all shards, microseconds (0: none yet)
+    pub latency_us: u64,
+    pub per_shard: Vec<ShardStats>,
+}
+
+struct Queued {
+    agent_id: Uuid,
+    job: Job,
+}
+
+#[derive(Default)]
+struct Lanes {
+    realtime: VecDeque<Queued>,
+    backlog: VecDeque<Queued>,
+    /// Lane and number of queued units of work per agent; an agent's units share one lane
+    agents: HashMap<Uuid, (Lane, usize)>,
+    /// Realtime units of work run since the last backlog unit
+    streak: usize,
+}
+
+impl Lanes {
+    fn len(&self) -> usize {
+        self.realtime.len() + self.backlog.len()
+    }
+
+    /// Queue in `lane`, or in the lane the agent's queued units already wait in.
+    fn push(&mut self, agent_id: Uuid, lane: Lane, job: Job) -> Lane {
+        let entry = self.agents.entry(agent_id).or_insert((lane, 0));
+        entry.1 += 1;
+        let lane = entry.0;
+        let queued = Queued { agent_id, job };
+        match lane {
+            Lane::Realtime => self.realtime.push_back(queued),
+            Lane::Backlog => self.backlog.push_back(queued),
+        }
+        lane
+    }
+
+    /// Next unit of work: realtime first, a backlog unit after every `ratio` realtime units.
+    fn pop(&mut self, ratio: usize) -> Option<(Lane, Job)> {
+        let lane = match (self.realtime.is_empty(), self.backlog.is_empty()) {
+            (true, true) => return None,
+            (false, true) => Lane::Realtime,
+            (true, false) => Lane::Backlog,
+            (false, false) if self.streak >= ratio => Lane::Backlog,
+            (false, false) => Lane::Realtime,
+        };
+        let queued = match lane {
+            Lane::Realtime => {
+                self.streak += 1;
+                self.realtime.pop_front()
+            }
+            Lane::Backlog => {
+                self.streak = 0;
+                self.backlog.pop_front()
+            }
+        }?;
+        if let Some(entry) = self.agents.get_mut(&queued.agent_id) {
+            entry.1 -= 1;
+            if entry.1 == 0 {
+                self.agents.remove(&queued.agent_id);
+            }
+        }
+        Some((lane, queued.job))
+    }
+}
+
+struct Shard {
+    lanes: Arc<Mutex<Lanes>>,
+    ready: Arc<Notify>,
+    processed: Arc<AtomicU64>,
+    backlog_processed: Arc<AtomicU64>,
+    refused: AtomicU64,
+    latency_us: Arc<AtomicU64>,
+}
+
+pub struct ShardedPipeline {
+    cfg: PipelineConfig,
+    shards: Vec<Shard>,
+    latency_us: Arc<AtomicU64>,
+    started: AtomicBool,
+}
+
+impl ShardedPipeline {
+    /// Queues exist from construction; nothing runs until `start`.
+    pub fn new(cfg: PipelineConfig) -> Self {
+        let shards = cfg.shards.clamp(1, MAX_PIPELINE_SHARDS);
+        let cfg = PipelineConfig {
+            shards,
+            queue_depth: cfg.queue_depth.max(1),
+            realtime_ratio: cfg.realtime_ratio.max(1),
+            ..cfg
+        };
+        let shards = (0..shards)
+            .map(|_| Shard {
+                lanes: Arc::new(Mutex::new(Lanes::default())),
+                ready: Arc::new(Notify::new()),
+                processed: Arc::new(AtomicU64::new(0)),
+                backlog_processed: Arc::new(AtomicU64::new(0)),
+                refused: AtomicU64::new(0),
+                latency_us: Arc::new(AtomicU64::new(0)),
+            })
+            .collect();
+        Self { cfg, shards, latency_us: Arc::new(AtomicU64::new(0)), started: AtomicBool::new(false) }
+    }
+
+    pub fn config(&self) -> PipelineConfig {
+        self.cfg
+    }
+
+    /// Spawn one worker per shard on its store (stores.len() == shards). Err if already started.
+    pub fn start(&self, stores: Vec<Arc<dyn TelemetryStore>>) -> Result<(), String> {
+        if stores.len() != self.shards.len() {
+            return Err(format!("pipeline needs {} stores, got {}", self.shards.len(), stores.len()));
+        }
+        if self.started.swap(true, Ordering::AcqRel) {
+            return Err("pipeline already started".to_string());
+        }
+        for (index, (shard, store)) in self.shards.iter().zip(stores).enumerate() {
+            let lanes = shard.lanes.clone();
+            let ready = shard.ready.clone();
+            let processed = shard.processed.clone();
+            let backlog_processed = shard.backlog_processed.clone();
+            let shard_latency = shard.latency_us.clone();
+            let latency = self.latency_us.clone();
+            let ratio = self.cfg.realtime_ratio;
+            tokio::spawn(async move {
+                loop {
+                    let next = lanes.lock().pop(ratio);
+                    let Some((lane, job)) = next else {
+                        ready.notified().await;
+                        continue;
+                    };
+                    let started = Instant::now();
+                    // Own task: a panic fails this unit of work (its caller sees Unavailable), not the shard
+                    if let Err(e) = tokio::spawn(job(store.clone())).await {
+                        error!("Ingest pipeline shard {} unit of work failed: {}", index, e);
+                    }
+                    observe_latency(&shard_latency, started.elapsed());
+                    observe_latency(&latency, started.elapsed());
+                    processed.fetch_add(1, Ordering::Relaxed);
+                    if lane == Lane::Backlog {
+                        backlog_processed.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            });
+        }
+        info!(
+            "Ingest pipeline started | shards={} | queue_depth={} | backlog_age_secs={} | realtime_ratio={}",
+            self.cfg.shards,
+            self.cfg.queue_depth,
+            self.cfg.backlog_age.as_secs(),
+            self.cfg.realtime_ratio
+        );
+        Ok(())
+    }
+
+    /// Run `work` on the agent's shard after everything queued before it for that shard, in the
+    /// realtime lane.
+    pub async fn run<T, F, Fut>(&self, agent_id: Uuid, work: F) -> Result<T, PipelineError>
+    where
+        T: Send + 'static,
+        F: FnOnce(Arc<dyn TelemetryStore>) -> Fut + Send + 'static,
+        Fut: Future<Output = T> + Send + 'static,
+    {
+        self.run_in(agent_id, Lane::Realtime, work).await
+    }
+
+    /// Run `work` for an event observed at `observed_at`: in the backlog lane once it is older
+    /// than the backlog age, after the agent's earlier events either way.
+    pub async fn run_event<T, F, Fut>(&self, agent_id: Uuid, observed_at: DateTime<Utc>, work: F) -> Result<T, PipelineError>
+    where
+        T: Send + 'static,
+        F: FnOnce(Arc<dyn TelemetryStore>) -> Fut + Send + 'static,
+        Fut: Future<Output = T> + Send + 'static,
+    {
+        self.run_in(agent_id, self.lane_for(observed_at, Utc::now()), work).await
+    }
+
+    /// Lane of an event observed at `observed_at`, as of `now`.
+    pub fn lane_for(&self, observed_at: DateTime<Utc>, now: DateTime<Utc>) -> Lane {
+        match (now - observed_at).to_std() {
+            Ok(age) if age > self.cfg.backlog_age => Lane::Backlog,
+            _ => Lane::Realtime,
+        }
+    }
+
+    async fn run_in<T, F, Fut>(&self, agent_id: Uuid, lane: Lane, work: F) -> Result<T, PipelineError>
+    where
+        T: Send + 'static,
+        F: FnOnce(Arc<dyn TelemetryStore>) -> Fut + Send + 'static,
+        Fut: Future<Output = T> + Send + 'static,
+    {
+        let index = shard_for(agent_id, self.shards.len());
+        let shard = &self.shards[index];
+        let (reply_tx, reply_rx) = oneshot::channel();
+        let job: Job = Box::new(move |store| {
+            Box::pin(async move {
+                let _ = reply_tx.send(work(store).await);
+            })
+        });
+        {
+            let mut lanes = shard.lanes.lock();
+            if lanes.len() >= self.cfg.queue_depth {
+                drop(lanes);
+                shard.refused.fetch_add(1, Ordering::Relaxed);
+                warn!("Ingest pipeline shard {} full ({} queued) | agent_id={}", index, self.cfg.queue_depth, agent_id);
+                return Err(PipelineError::Busy);
+            }
+            lanes.push(agent_id, lane, job);
+        }
+        shard.ready.notify_one();
+        reply_rx.await.map_err(|_| PipelineError::Unavailable)
+    }
+
+    /// Queue depths and latencies as seen by `agent_id`'s next event.
+    pub fn load(&self, agent_id: Uuid) -> PipelineLoad {
+        let shard = &self.shards[shard_for(agent_id, self.shards.len())];
+        let shard_queued = shard.lanes.lock().len();
+        PipelineLoad {
+            shard_queued,
+            queued: self.shards.iter().map(|s| s.lanes.lock().len()).sum(),
+            queue_depth: self.cfg.queue_depth,
+            shards: self.shards.len(),
+            latency: Duration::from_micros(self.latency_us.load(Ordering::Relaxed)),
+            shard_latency: Duration::from_micros(shard.latency_us.load(Ordering::Relaxed)),
+        }
+    }
+
+    pub fn stats(&self) -> PipelineStats {
+        PipelineStats {
+            shards: self.cfg.shards,
+            queue_depth: self.cfg.queue_depth,
+            backlog_age_secs: self.cfg.backlog_age.as_secs(),
+            realtime_ratio: self.cfg.realtime_ratio,
+            started: self.started.load(Ordering::Acquire),
+            latency_us: self.latency_us.load(Ordering::Relaxed),
+            per_shard: self
+                .shards
+                .iter()
+                .enumerate()
+                .map(|(shard, s)| {
+                    let (queued, backlog_queued) = {
+                        let lanes = s.lanes.lock();
+                        (lanes.len(), lanes.backlog.len())
+                    };
+                    ShardStats {
+                        shard,
+                        queued,
+                        backlog_queued,
+                        processed: s.processed.load(Ordering::Relaxed),
+                        backlog_processed: s.backlog_processed.load(Ordering::Relaxed),
+                        refused: s.refused.load(Ordering::Relaxed),
+                        latency_us: s.latency_us.load(Ordering::Relaxed),
+                    }
+                })
+                .collect(),
+        }
+    }
+}
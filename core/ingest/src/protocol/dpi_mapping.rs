@@ -0,0 +1,351 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_ingestion/src/protocol/dpi_mapping.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Declarative DPI envelope.data -> dpi_probe_telemetry column mapping, versioned with the envelope schema
+
+/*
+ * DPI Field Mapping
+ *
+ * Which probe fields land in which dpi_probe_telemetry columns is data, not handler code:
+ * protocol/dpi_field_mapping_v<N>.json lists, per target column, the envelope.data paths to
+ * read (dotted, first present wins). A new probe field (SNI, JA3, DNS, ...) is a mapping edit.
+ *
+ * Targets are either a typed column (coerced: inet / port / bigint / text) or
+ * `metadata.<key>`, collected into the metadata jsonb column as received. A value that does
+ * not coerce is left NULL and counted; the event is still stored in full in `payload`.
+ *
+ * The mapping is selected by envelope `schema_version` (absent = 1). An envelope whose version
+ * has no mapping is rejected, like any other incompatible schema version.
+ *
+ * RANSOMEYE_DPI_FIELD_MAPPING_PATH adds or replaces a version from a file at startup
+ * (FAIL-CLOSED if the file is unreadable or invalid).
+ */
+
+use std::collections::{BTreeMap, HashSet};
+use std::net::IpAddr;
+
+use serde::Deserialize;
+use serde_json::{Map, Value as JsonValue};
+
+const BUILTIN_MAPPINGS: &[&str] = &[include_str!("dpi_field_mapping_v1.json")];
+
+/// Longest text value stored in a column; longer values are truncated on a char boundary
+const MAX_TEXT_LEN: usize = 2048;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DpiColumn {
+    SrcIp,
+    SrcPort,
+    DstIp,
+    DstPort,
+    Protocol,
+    IfaceName,
+    FlowId,
+    PacketCount,
+    ByteCount,
+    BytesIn,
+    BytesOut,
+    PacketsIn,
+    PacketsOut,
+    TlsSni,
+    Ja3,
+    Ja3s,
+    HttpHost,
+    HttpMethod,
+    HttpPath,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColumnKind {
+    Inet,
+    Port,
+    BigInt,
+    Text,
+}
+
+impl DpiColumn {
+    pub fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "src_ip" => DpiColumn::SrcIp,
+            "src_port" => DpiColumn::SrcPort,
+            "dst_ip" => DpiColumn::DstIp,
+            "dst_port" => DpiColumn::DstPort,
+            "protocol" => DpiColumn::Protocol,
+            "iface_name" => DpiColumn::IfaceName,
+            "flow_id" => DpiColumn::FlowId,
+            "packet_count" => DpiColumn::PacketCount,
+            "byte_count" => DpiColumn::ByteCount,
+            "bytes_in" => DpiColumn::BytesIn,
+            "bytes_out" => DpiColumn::BytesOut,
+            "packets_in" => DpiColumn::PacketsIn,
+            "packets_out" => DpiColumn::PacketsOut,
+            "tls_sni" => DpiColumn::TlsSni,
+            "ja3" => DpiColumn::Ja3,
+            "ja3s" => DpiColumn::Ja3s,
+            "http_host" => DpiColumn::HttpHost,
+            "http_method" => DpiColumn::HttpMethod,
+            "http_path" => DpiColumn::HttpPath,
+            _ => return None,
+        })
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DpiColumn::SrcIp => "src_ip",
+            DpiColumn::SrcPort => "src_port",
+            DpiColumn::DstIp => "dst_ip",
+            DpiColumn::DstPort => "dst_port",
+            DpiColumn::Protocol => "protocol",
+            DpiColumn::IfaceName => "iface_name",
+            DpiColumn::FlowId => "flow_id",
+            DpiColumn::PacketCount => "packet_count",
+            DpiColumn::ByteCount => "byte_count",
+            DpiColumn::BytesIn => "bytes_in",
+            DpiColumn::BytesOut => "bytes_out",
+            DpiColumn::PacketsIn => "packets_in",
+            DpiColumn::PacketsOut => "packets_out",
+            DpiColumn::TlsSni => "tls_sni",
+            DpiColumn::Ja3 => "ja3",
+            DpiColumn::Ja3s => "ja3s",
+            DpiColumn::HttpHost => "http_host",
+            DpiColumn::HttpMethod => "http_method",
+            DpiColumn::HttpPath => "http_path",
+        }
+    }
+
+    fn kind(&self) -> ColumnKind {
+        match self {
+            DpiColumn::SrcIp | DpiColumn::DstIp => ColumnKind::Inet,
+            DpiColumn::SrcPort | DpiColumn::DstPort => ColumnKind::Port,
+            DpiColumn::PacketCount | DpiColumn::ByteCount | DpiColumn::BytesIn | DpiColumn::BytesOut
+            | DpiColumn::PacketsIn | DpiColumn::PacketsOut => ColumnKind::BigInt,
+            _ => ColumnKind::Text,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DpiTarget {
+    Column(DpiColumn),
+    /// Key in the metadata jsonb column
+    Metadata(String),
+}
+
+impl DpiTarget {
+    pub fn parse(target: &str) -> Result<Self, String> {
+        if let Some(key) = target.strip_prefix("metadata.") {
+            if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return Err(format!("invalid metadata key in target '{}'", target));
+            }
+            return Ok(DpiTarget::Metadata(key.to_string()));
+        }
+        DpiColumn::parse(target)
+            .map(DpiTarget::Column)
+            .ok_or_else(|| format!("unknown dpi_probe_telemetry column '{}'", target))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct MappingFile {
+    schema_version: u32,
+    fields: Vec<FieldEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FieldEntry {
+    target: String,
+    paths: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldRule {
+    pub target: DpiTarget,
+    /// Dotted envelope.data paths, first present (non-null) wins
+    pub paths: Vec<Vec<String>>,
+}
+
+/// Mapping for one envelope schema version
+#[derive(Debug, Clone)]
+pub struct DpiFieldMapping {
+    pub schema_version: u32,
+    pub rules: Vec<FieldRule>,
+}
+
+/// Values extracted from one envelope.data
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DpiFields {
+    /// Already validated as IP addresses
+    pub src_ip: Option<String>,
+    pub src_port: Option<i32>,
+    pub dst_ip: Option<String>,
+    pub dst_port: Option<i32>,
+    pub protocol: Option<String>,
+    pub iface_name: Option<String>,
+    pub flow_id: Option<String>,
+    pub packet_count: Option<i64>,
+    pub byte_count: Option<i64>,
+    pub bytes_in: Option<i64>,
+    pub bytes_out: Option<i64>,
+    pub packets_in: Option<i64>,
+    pub packets_out: Option<i64>,
+    pub tls_sni: Option<String>,
+    pub ja3: Option<String>,
+    pub ja3s: Option<String>,
+    pub http_host: Option<String>,
+    pub http_method: Option<String>,
+    pub http_path: Option<String>,
+    /// metadata.<key> targets; None when no such field was present
+    pub metadata: Option<Map<String, JsonValue>>,
+    /// Columns whose value was present but did not coerce to the column type
+    pub rejected: Vec<&'static str>,
+}
+
+impl DpiFieldMapping {
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let file: MappingFile = serde_json::from_str(json).map_err(|e| format!("invalid DPI field mapping: {}", e))?;
+        if file.schema_version == 0 {
+            return Err("DPI field mapping schema_version must be >= 1".to_string());
+        }
+        let mut seen = HashSet::new();
+        let mut rules = Vec::with_capacity(file.fields.len());
+        for entry in file.fields {
+            let target = DpiTarget::parse(&entry.target)?;
+            if !seen.insert(target.clone()) {
+                return Err(format!("DPI field mapping v{}: target '{}' mapped twice", file.schema_version, entry.target));
+            }
+            if entry.paths.is_empty() {
+                return Err(format!("DPI field mapping v{}: target '{}' has no paths", file.schema_version, entry.target));
+            }
+            let mut paths = Vec::with_capacity(entry.paths.len());
+            for path in &entry.paths {
+                let segments: Vec<String> = path.split('.').map(str::to_string).collect();
+                if segments.iter().any(|s| s.is_empty()) {
+                    return Err(format!("DPI field mapping v{}: invalid path '{}'", file.schema_version, path));
+                }
+                paths.push(segments);
+            }
+            rules.push(FieldRule { target, paths });
+        }
+        Ok(Self { schema_version: file.schema_version, rules })
+    }
+
+    pub fn extract(&self, data: &JsonValue) -> DpiFields {
+        let mut fields = DpiFields::default();
+        for rule in &self.rules {
+            let Some(value) = rule.paths.iter().find_map(|p| lookup(data, p)) else { continue };
+            match &rule.target {
+                DpiTarget::Metadata(key) => {
+                    fields.metadata.get_or_insert_with(Map::new).insert(key.clone(), value.clone());
+                }
+                DpiTarget::Column(column) => {
+                    if !fields.set(*column, value) {
+                        fields.rejected.push(column.as_str());
+                    }
+                }
+            }
+        }
+        fields
+    }
+}
+
+impl DpiFields {
+    /// Coerce and store; false if the value does not fit the column
+    fn set(&mut self, column: DpiColumn, value: &JsonValue) -> bool {
+        match column.kind() {
+            ColumnKind::Inet => {
+                let Some(ip) = value.as_str().and_then(|s| s.parse::<IpAddr>().ok()) else { return false };
+                let slot = if column == DpiColumn::SrcIp { &mut self.src_ip } else { &mut self.dst_ip };
+                *slot = Some(ip.to_string());
+            }
+            ColumnKind::Port => {
+                let Some(port) = value.as_u64().filter(|p| *p <= u16::MAX as u64) else { return false };
+                let slot = if column == DpiColumn::SrcPort { &mut self.src_port } else { &mut self.dst_port };
+                *slot = Some(port as i32);
+            }
+            ColumnKind::BigInt => {
+                let Some(n) = value.as_i64() else { return false };
+                let slot = match column {
+                    DpiColumn::PacketCount => &mut self.packet_count,
+                    DpiColumn::ByteCount => &mut self.byte_count,
+                    DpiColumn::BytesIn => &mut self.bytes_in,
+                    DpiColumn::BytesOut => &mut self.bytes_out,
+                    DpiColumn::PacketsIn => &mut self.packets_in,
+                    _ => &mut self.packets_out,
+                };
+                *slot = Some(n);
+            }
+            ColumnKind::Text => {
+                let Some(s) = value.as_str() else { return false };
+                let slot = match column {
+                    DpiColumn::Protocol => &mut self.protocol,
+                    DpiColumn::IfaceName => &mut self.iface_name,
+                    DpiColumn::FlowId => &mut self.flow_id,
+                    DpiColumn::TlsSni => &mut self.tls_sni,
+                    DpiColumn::Ja3 => &mut self.ja3,
+                    DpiColumn::Ja3s => &mut self.ja3s,
+                    DpiColumn::HttpHost => &mut self.http_host,
+                    DpiColumn::HttpMethod => &mut self.http_method,
+                    _ => &mut self.http_path,
+                };
+                *slot = Some(truncate_text(s));
+            }
+        }
+        true
+    }
+}
+
+fn lookup<'v>(data: &'v JsonValue, path: &[String]) -> Option<&'v JsonValue> {
+    let mut node = data;
+    for segment in path {
+        node = node.as_object()?.get(segment)?;
+    }
+    (!node.is_null()).then_some(node)
+}
+
+fn truncate_text(s: &str) -> String {
+    if s.len() <= MAX_TEXT_LEN {
+        return s.to_string();
+    }
+    let mut end = MAX_TEXT_LEN;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    s[..end].to_string()
+}
+
+/// All known mappings by envelope schema version
+#[derive(Debug, Clone)]
+pub struct DpiFieldMappings {
+    by_version: BTreeMap<u32, DpiFieldMapping>,
+}
+
+impl DpiFieldMappings {
+    /// Mappings shipped with this build (protocol/dpi_field_mapping_v*.json)
+    pub fn builtin() -> Result<Self, String> {
+        let mut by_version = BTreeMap::new();
+        for json in BUILTIN_MAPPINGS {
+            let mapping = DpiFieldMapping::from_json(json)?;
+            by_version.insert(mapping.schema_version, mapping);
+        }
+        Ok(Self { by_version })
+    }
+
+    pub fn from_env() -> Result<Self, String> {
+        let mut mappings = Self::builtin()?;
+        if let Ok(path) = std::env::var("RANSOMEYE_DPI_FIELD_MAPPING_PATH") {
+            let json = std::fs::read_to_string(&path)
+                .map_err(|e| format!("Cannot read RANSOMEYE_DPI_FIELD_MAPPING_PATH {}: {}", path, e))?;
+            let mapping = DpiFieldMapping::from_json(&json).map_err(|e| format!("{}: {}", path, e))?;
+            mappings.by_version.insert(mapping.schema_version, mapping);
+        }
+        Ok(mappings)
+    }
+
+    /// Mapping for an envelope's schema_version (absent = 1)
+    pub fn for_version(&self, schema_version: Option<u32>) -> Option<&DpiFieldMapping> {
+        self.by_version.get(&schema_version.unwrap_or(1))
+    }
+
+    pub fn versions(&self) -> Vec<u32> {
+        self.by_version.keys().copied().collect()
+    }
+}
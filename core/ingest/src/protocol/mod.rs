@@ -2,5 +2,8 @@
 // Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
 // Details of functionality of this file: Protocol module exports
 
+pub mod dpi_mapping;
 pub mod event_envelope;
+pub mod signed_event;
+pub mod windows_mapping;
 
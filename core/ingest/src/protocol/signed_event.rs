@@ -0,0 +1,204 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_ingestion/src/protocol/signed_event.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Typed, borrowing view of the SignedEvent wire format posted to /ingest/linux and /ingest/dpi
+
+/*
+ * Signed Event Wire Format
+ *
+ * The HTTP ingest hot path parses each request body exactly once into these types:
+ *   - header strings borrow from the request body (Cow: owned only if JSON-escaped)
+ *   - `envelope` and `data` stay as RawValue slices of the body, so the bytes that are
+ *     hashed and stored are the bytes that were received - no serde_json::Value tree,
+ *     no re-serialization
+ * Only the telemetry fields ingest actually extracts are deserialized into owned values.
+ * Unknown fields are ignored, as with the previous Value-based extraction.
+ */
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use serde::Deserialize;
+use serde_json::value::RawValue;
+
+/// Transport wrapper sent by agents and probes.
+#[derive(Debug, Deserialize)]
+pub struct SignedEventRef<'a> {
+    /// EventEnvelope JSON, kept verbatim
+    #[serde(borrow)]
+    pub envelope: &'a RawValue,
+    /// SHA-256 hex of canonical envelope JSON bytes
+    #[serde(borrow)]
+    pub payload_hash: Cow<'a, str>,
+    /// Base64 signature of payload_hash
+    #[serde(borrow)]
+    pub signature: Cow<'a, str>,
+    /// Key identifier
+    #[serde(borrow)]
+    pub signer_id: Cow<'a, str>,
+    /// Signer sequence number the signature was made under (absent: signed over the hash alone)
+    #[serde(default)]
+    pub sequence: Option<u64>,
+}
+
+impl<'a> SignedEventRef<'a> {
+    pub fn parse(body: &'a [u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(body)
+    }
+
+    pub fn envelope(&self) -> Result<EnvelopeRef<'a>, serde_json::Error> {
+        serde_json::from_str(self.envelope.get())
+    }
+}
+
+/// Envelope header fields ingest relies on; `data` stays raw until the source-specific view.
+#[derive(Debug, Deserialize)]
+pub struct EnvelopeRef<'a> {
+    #[serde(borrow)]
+    pub event_id: Cow<'a, str>,
+    #[serde(borrow)]
+    pub timestamp: Cow<'a, str>,
+    #[serde(borrow)]
+    pub component_id: Cow<'a, str>,
+    /// Producer-reported host identifier (optional; used for duplicate agent-id detection)
+    #[serde(borrow, default)]
+    pub host_id: Option<Cow<'a, str>>,
+    /// Envelope schema version (absent = 1); selects the DPI field mapping
+    #[serde(default)]
+    pub schema_version: Option<u32>,
+    #[serde(borrow)]
+    pub data: &'a RawValue,
+}
+
+impl<'a> EnvelopeRef<'a> {
+    pub fn data<T: Deserialize<'a>>(&self) -> Result<T, serde_json::Error> {
+        serde_json::from_str(self.data.get())
+    }
+}
+
+/// Linux agent `envelope.data` fields extracted into linux_agent_telemetry.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct LinuxEventData {
+    pub event_category: Option<String>,
+    pub pid: Option<u64>,
+    pub uid: Option<u64>,
+    pub gid: Option<u64>,
+    pub process_data: Option<LinuxProcessData>,
+    pub filesystem_data: Option<LinuxFilesystemData>,
+    pub network_data: Option<LinuxNetworkData>,
+    /// Container the process runs in; absent for host activity
+    pub container: Option<LinuxContainerData>,
+    /// Periodic agent self-report (event_category agent_stats)
+    pub agent_stats: Option<LinuxAgentStats>,
+    /// Host facts reported at agent startup and daily (event_category host_inventory)
+    pub host_inventory: Option<LinuxHostInventory>,
+}
+
+impl LinuxEventData {
+    /// Agent sub-type of the event (e.g. Exec, MassWrite, Connect) from whichever section is present
+    pub fn event_type(&self) -> Option<&str> {
+        self.filesystem_data.as_ref().and_then(|f| f.event_type.as_deref())
+            .or_else(|| self.process_data.as_ref().and_then(|p| p.event_type.as_deref()))
+            .or_else(|| self.network_data.as_ref().and_then(|n| n.event_type.as_deref()))
+    }
+}
+
+/// Parts of an agent_stats report ingest persists (the rest stays in the telemetry payload).
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct LinuxAgentStats {
+    /// Agent process run; the cumulative counters restart from zero with each run
+    pub run_id: Option<String>,
+    /// Cumulative events lost on the agent per reason since the run started
+    pub dropped_by_reason: Option<BTreeMap<String, u64>>,
+}
+
+/// Host inventory report, validated into a host_inventory row (see crate::host_inventory).
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct LinuxHostInventory {
+    pub hostname: Option<String>,
+    pub os_name: Option<String>,
+    pub os_version: Option<String>,
+    pub kernel_release: Option<String>,
+    pub kernel_version: Option<String>,
+    pub arch: Option<String>,
+    pub cpu_model: Option<String>,
+    pub cpu_cores: Option<u32>,
+    pub memory_total_bytes: Option<u64>,
+    pub security_tools: Vec<LinuxSecurityTool>,
+    /// encrypted, partial, none or unknown
+    pub disk_encryption: Option<String>,
+    pub encrypted_volumes: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct LinuxSecurityTool {
+    pub name: String,
+    pub kind: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct LinuxProcessData {
+    pub event_type: Option<String>,
+    pub ppid: Option<u64>,
+    pub executable: Option<String>,
+    pub command_line: Option<String>,
+    pub mmap_address: Option<u64>,
+    pub mmap_size: Option<u64>,
+    /// Per-boot process lineage link (verified by crate::lineage)
+    pub lineage: Option<LinuxLineage>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct LinuxLineage {
+    pub boot_id: String,
+    pub chain_id: String,
+    pub hash: String,
+    pub parent_hash: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct LinuxContainerData {
+    pub container_id: Option<String>,
+    pub runtime: Option<String>,
+    pub image: Option<String>,
+    pub container_name: Option<String>,
+    pub pod_name: Option<String>,
+    pub pod_namespace: Option<String>,
+    pub pod_uid: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct LinuxFilesystemData {
+    pub event_type: Option<String>,
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct LinuxNetworkData {
+    pub event_type: Option<String>,
+    pub remote_addr: Option<String>,
+    pub remote_port: Option<u64>,
+    pub local_addr: Option<String>,
+    pub local_port: Option<u64>,
+    /// Network namespace inode of the socket
+    pub netns_inode: Option<u64>,
+    pub host_netns: Option<bool>,
+}
+
+/// DPI probe flow tuple of `envelope.data`. Column extraction goes through protocol::dpi_mapping.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct DpiEventData {
+    pub src_ip: Option<String>,
+    pub src_port: Option<u64>,
+    pub dst_ip: Option<String>,
+    pub dst_port: Option<u64>,
+    pub protocol: Option<String>,
+}
@@ -0,0 +1,504 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_ingestion/src/protocol/windows_mapping.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Declarative Windows event log (channel + event ID) -> agent envelope.data mapping for Security, System and Sysmon events
+
+/*
+ * Windows Event Mapping
+ *
+ * Windows telemetry arrives as event log records: a channel, an event ID and a flat EventData
+ * name/value map. protocol/windows_event_mapping_v<N>.json lists, per (channel, event ID), the
+ * event_category / event_type it becomes and which EventData names fill which envelope.data
+ * fields (first present wins). Covering another event ID is a mapping edit.
+ *
+ * The result is shaped like the agents' envelope.data: `event_category`, `pid`, the category's
+ * `<category>_data` section carrying `event_type`, `features.event_type`, and a `windows`
+ * section with the record identity, Windows-only fields (accounts, logon, services, tasks) and
+ * the EventData as received. Categories without an agent section (authentication, service,
+ * scheduled_task, audit_log) carry their fields under `windows` only.
+ *
+ * Targets are `pid`, `<section>.<key>` with section one of process_data, filesystem_data,
+ * registry_data, network_data or windows. Values are coerced per rule (text / pid / int / ip /
+ * port / bool / sha256); Windows' "-" and "" placeholders count as absent. A value that does
+ * not coerce is dropped and reported; the raw EventData keeps it.
+ *
+ * Records with no mapping still map: category `windows_event`, type `Unmapped`, with the
+ * `windows` section only, so nothing is lost before the mapping catches up.
+ */
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr};
+
+use serde::Deserialize;
+use serde_json::{json, Map, Value as JsonValue};
+
+const BUILTIN_MAPPINGS: &[&str] = &[include_str!("windows_event_mapping_v1.json")];
+
+/// Longest text value kept in a mapped field (task XML, command lines); longer values are
+/// truncated on a char boundary
+const MAX_TEXT_LEN: usize = 8192;
+
+/// Category and type given to records without a mapping entry
+pub const UNMAPPED_CATEGORY: &str = "windows_event";
+pub const UNMAPPED_EVENT_TYPE: &str = "Unmapped";
+
+/// Record identity keys in the `windows` section; not available as targets
+const WINDOWS_RESERVED_KEYS: &[&str] =
+    &["channel", "provider", "event_id", "record_id", "computer", "time_created", "event_data"];
+
+/// One event log record as read from the channel (EventData rendered to name/value pairs)
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WindowsEventRecord {
+    pub channel: String,
+    #[serde(default)]
+    pub provider: Option<String>,
+    pub event_id: u32,
+    #[serde(default)]
+    pub record_id: Option<u64>,
+    /// System/TimeCreated as rendered by the agent (RFC 3339)
+    #[serde(default)]
+    pub time_created: Option<String>,
+    #[serde(default)]
+    pub computer: Option<String>,
+    #[serde(default)]
+    pub event_data: Map<String, JsonValue>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WindowsSection {
+    ProcessData,
+    FilesystemData,
+    RegistryData,
+    NetworkData,
+    Windows,
+}
+
+impl WindowsSection {
+    pub fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "process_data" => WindowsSection::ProcessData,
+            "filesystem_data" => WindowsSection::FilesystemData,
+            "registry_data" => WindowsSection::RegistryData,
+            "network_data" => WindowsSection::NetworkData,
+            "windows" => WindowsSection::Windows,
+            _ => return None,
+        })
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WindowsSection::ProcessData => "process_data",
+            WindowsSection::FilesystemData => "filesystem_data",
+            WindowsSection::RegistryData => "registry_data",
+            WindowsSection::NetworkData => "network_data",
+            WindowsSection::Windows => "windows",
+        }
+    }
+
+    /// Agent section that carries `event_type` for a category, if the agents have one
+    fn for_category(category: &str) -> Option<Self> {
+        Some(match category {
+            "process" => WindowsSection::ProcessData,
+            "filesystem" => WindowsSection::FilesystemData,
+            "registry" => WindowsSection::RegistryData,
+            "network" => WindowsSection::NetworkData,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum WindowsTarget {
+    Pid,
+    Field { section: WindowsSection, key: String },
+}
+
+impl WindowsTarget {
+    pub fn parse(target: &str) -> Result<Self, String> {
+        if target == "pid" {
+            return Ok(WindowsTarget::Pid);
+        }
+        let (section, key) = target.split_once('.').ok_or_else(|| format!("invalid target '{}'", target))?;
+        let section = WindowsSection::parse(section).ok_or_else(|| format!("unknown section in target '{}'", target))?;
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_') {
+            return Err(format!("invalid key in target '{}'", target));
+        }
+        let reserved = match section {
+            WindowsSection::Windows => WINDOWS_RESERVED_KEYS.contains(&key),
+            _ => key == "event_type",
+        };
+        if reserved {
+            return Err(format!("target '{}' is set by the mapper", target));
+        }
+        Ok(WindowsTarget::Field { section, key: key.to_string() })
+    }
+
+    pub fn as_string(&self) -> String {
+        match self {
+            WindowsTarget::Pid => "pid".to_string(),
+            WindowsTarget::Field { section, key } => format!("{}.{}", section.as_str(), key),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Coerce {
+    Text,
+    /// Decimal, or 0x-prefixed hex as the Security channel writes process IDs
+    Pid,
+    Int,
+    /// IPv4-mapped IPv6 addresses are reduced to IPv4
+    Ip,
+    Port,
+    Bool,
+    /// Bare SHA-256, or the SHA256= entry of a Sysmon `Hashes` list
+    Sha256,
+}
+
+impl Coerce {
+    pub fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "text" => Coerce::Text,
+            "pid" => Coerce::Pid,
+            "int" => Coerce::Int,
+            "ip" => Coerce::Ip,
+            "port" => Coerce::Port,
+            "bool" => Coerce::Bool,
+            "sha256" => Coerce::Sha256,
+            _ => return None,
+        })
+    }
+
+    /// Ok(None) when the value is present but carries nothing (a Hashes list without SHA256);
+    /// Err when it does not fit
+    fn apply(&self, value: &JsonValue) -> Result<Option<JsonValue>, ()> {
+        let text = value.as_str().map(str::trim);
+        match self {
+            Coerce::Text => match value {
+                JsonValue::String(s) => Ok(Some(JsonValue::String(truncate_text(s)))),
+                JsonValue::Number(_) | JsonValue::Bool(_) => Ok(Some(JsonValue::String(value.to_string()))),
+                _ => Err(()),
+            },
+            Coerce::Pid => {
+                if let Some(n) = value.as_u64() {
+                    return Ok(Some(json!(n)));
+                }
+                let s = text.ok_or(())?;
+                let parsed = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+                    Some(hex) => u64::from_str_radix(hex, 16),
+                    None => s.parse::<u64>(),
+                };
+                parsed.map(|n| Some(json!(n))).map_err(|_| ())
+            }
+            Coerce::Int => match (value.as_i64(), text) {
+                (Some(n), _) => Ok(Some(json!(n))),
+                (None, Some(s)) => s.parse::<i64>().map(|n| Some(json!(n))).map_err(|_| ()),
+                _ => Err(()),
+            },
+            Coerce::Ip => {
+                let s = text.ok_or(())?;
+                let ip = match s.strip_prefix("::ffff:").and_then(|v4| v4.parse::<Ipv4Addr>().ok()) {
+                    Some(v4) => IpAddr::V4(v4),
+                    None => s.parse::<IpAddr>().map_err(|_| ())?,
+                };
+                Ok(Some(JsonValue::String(ip.to_string())))
+            }
+            Coerce::Port => {
+                let port = match (value.as_u64(), text) {
+                    (Some(n), _) => n,
+                    (None, Some(s)) => s.parse::<u64>().map_err(|_| ())?,
+                    _ => return Err(()),
+                };
+                if port > u16::MAX as u64 {
+                    return Err(());
+                }
+                Ok(Some(json!(port)))
+            }
+            Coerce::Bool => match (value.as_bool(), text) {
+                (Some(b), _) => Ok(Some(json!(b))),
+                (None, Some(s)) if s.eq_ignore_ascii_case("true") => Ok(Some(json!(true))),
+                (None, Some(s)) if s.eq_ignore_ascii_case("false") => Ok(Some(json!(false))),
+                _ => Err(()),
+            },
+            Coerce::Sha256 => {
+                let s = text.ok_or(())?;
+                let digest = if s.contains('=') {
+                    let entry = s.split(',').find_map(|part| {
+                        let (algo, digest) = part.split_once('=')?;
+                        algo.trim().eq_ignore_ascii_case("SHA256").then(|| digest.trim())
+                    });
+                    match entry {
+                        Some(digest) => digest,
+                        None => return Ok(None),
+                    }
+                } else {
+                    s
+                };
+                if digest.len() != 64 || !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(());
+                }
+                Ok(Some(JsonValue::String(digest.to_ascii_lowercase())))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct MappingFile {
+    schema_version: u32,
+    events: Vec<EventEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EventEntry {
+    name: String,
+    channel: String,
+    event_id: u32,
+    event_category: String,
+    event_type: String,
+    fields: Vec<FieldEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FieldEntry {
+    target: String,
+    sources: Vec<String>,
+    #[serde(default)]
+    coerce: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldRule {
+    pub target: WindowsTarget,
+    /// EventData names, first present wins
+    pub sources: Vec<String>,
+    pub coerce: Coerce,
+}
+
+/// Mapping of one (channel, event ID)
+#[derive(Debug, Clone)]
+pub struct EventRule {
+    pub name: String,
+    pub channel: String,
+    pub event_id: u32,
+    pub event_category: String,
+    pub event_type: String,
+    pub fields: Vec<FieldRule>,
+}
+
+/// Mapping for one envelope schema version
+#[derive(Debug, Clone)]
+pub struct WindowsEventMapping {
+    pub schema_version: u32,
+    /// Keyed by (lowercased channel, event ID); channel names are case-insensitive on Windows
+    events: HashMap<(String, u32), EventRule>,
+}
+
+/// One record mapped into the normalized event model
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowsMappedEvent {
+    /// Name of the mapping entry used; None for unmapped records
+    pub rule: Option<String>,
+    pub event_category: String,
+    pub event_type: String,
+    /// Shaped like agent envelope.data
+    pub data: JsonValue,
+    /// Targets whose source was present but did not coerce
+    pub rejected: Vec<String>,
+}
+
+fn is_identifier(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+impl WindowsEventMapping {
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let file: MappingFile =
+            serde_json::from_str(json).map_err(|e| format!("invalid Windows event mapping: {}", e))?;
+        let version = file.schema_version;
+        if version == 0 {
+            return Err("Windows event mapping schema_version must be >= 1".to_string());
+        }
+        let mut events = HashMap::with_capacity(file.events.len());
+        let mut names = HashSet::new();
+        for entry in file.events {
+            let context = format!("Windows event mapping v{} '{}'", version, entry.name);
+            if !is_identifier(&entry.name) || !names.insert(entry.name.clone()) {
+                return Err(format!("Windows event mapping v{}: invalid or duplicate name '{}'", version, entry.name));
+            }
+            if entry.channel.trim().is_empty() {
+                return Err(format!("{}: empty channel", context));
+            }
+            if !is_identifier(&entry.event_category) || !is_identifier(&entry.event_type) {
+                return Err(format!("{}: event_category and event_type must be identifiers", context));
+            }
+            let mut seen = HashSet::new();
+            let mut fields = Vec::with_capacity(entry.fields.len());
+            for field in entry.fields {
+                let target = WindowsTarget::parse(&field.target).map_err(|e| format!("{}: {}", context, e))?;
+                if !seen.insert(target.clone()) {
+                    return Err(format!("{}: target '{}' mapped twice", context, field.target));
+                }
+                if field.sources.is_empty() || field.sources.iter().any(|s| s.is_empty()) {
+                    return Err(format!("{}: target '{}' needs non-empty sources", context, field.target));
+                }
+                let coerce = match field.coerce.as_deref() {
+                    None => Coerce::Text,
+                    Some(name) => Coerce::parse(name)
+                        .ok_or_else(|| format!("{}: unknown coerce '{}' for '{}'", context, name, field.target))?,
+                };
+                fields.push(FieldRule { target, sources: field.sources, coerce });
+            }
+            let key = (entry.channel.to_ascii_lowercase(), entry.event_id);
+            let rule = EventRule {
+                name: entry.name,
+                channel: entry.channel,
+                event_id: entry.event_id,
+                event_category: entry.event_category,
+                event_type: entry.event_type,
+                fields,
+            };
+            if events.insert(key, rule).is_some() {
+                return Err(format!("{}: channel and event_id mapped twice", context));
+            }
+        }
+        Ok(Self { schema_version: version, events })
+    }
+
+    pub fn rule(&self, channel: &str, event_id: u32) -> Option<&EventRule> {
+        self.events.get(&(channel.to_ascii_lowercase(), event_id))
+    }
+
+    /// Mapped (channel, event ID) pairs, sorted
+    pub fn covered(&self) -> Vec<(String, u32)> {
+        let mut covered: Vec<(String, u32)> =
+            self.events.values().map(|r| (r.channel.clone(), r.event_id)).collect();
+        covered.sort();
+        covered
+    }
+
+    /// Event categories the mapped records produce
+    pub fn categories(&self) -> BTreeSet<String> {
+        self.events.values().map(|r| r.event_category.clone()).collect()
+    }
+
+    pub fn map(&self, record: &WindowsEventRecord) -> WindowsMappedEvent {
+        let mut windows = Map::new();
+        windows.insert("channel".to_string(), json!(record.channel));
+        windows.insert("event_id".to_string(), json!(record.event_id));
+        for (key, value) in [("provider", &record.provider), ("time_created", &record.time_created), ("computer", &record.computer)] {
+            if let Some(value) = value {
+                windows.insert(key.to_string(), json!(value));
+            }
+        }
+        if let Some(record_id) = record.record_id {
+            windows.insert("record_id".to_string(), json!(record_id));
+        }
+
+        let Some(rule) = self.rule(&record.channel, record.event_id) else {
+            windows.insert("event_data".to_string(), JsonValue::Object(record.event_data.clone()));
+            return WindowsMappedEvent {
+                rule: None,
+                event_category: UNMAPPED_CATEGORY.to_string(),
+                event_type: UNMAPPED_EVENT_TYPE.to_string(),
+                data: json!({
+                    "event_category": UNMAPPED_CATEGORY,
+                    "features": { "event_type": UNMAPPED_EVENT_TYPE },
+                    "windows": windows,
+                }),
+                rejected: Vec::new(),
+            };
+        };
+
+        let mut data = Map::new();
+        let mut sections: BTreeMap<&'static str, Map<String, JsonValue>> = BTreeMap::new();
+        if let Some(section) = WindowsSection::for_category(&rule.event_category) {
+            sections.entry(section.as_str()).or_default().insert("event_type".to_string(), json!(rule.event_type));
+        }
+        let mut rejected = Vec::new();
+        for field in &rule.fields {
+            let Some(value) = field.sources.iter().find_map(|s| present(record.event_data.get(s))) else { continue };
+            let value = match field.coerce.apply(value) {
+                Ok(Some(value)) => value,
+                Ok(None) => continue,
+                Err(()) => {
+                    rejected.push(field.target.as_string());
+                    continue;
+                }
+            };
+            match &field.target {
+                WindowsTarget::Pid => {
+                    data.insert("pid".to_string(), value);
+                }
+                WindowsTarget::Field { section: WindowsSection::Windows, key } => {
+                    windows.insert(key.clone(), value);
+                }
+                WindowsTarget::Field { section, key } => {
+                    sections.entry(section.as_str()).or_default().insert(key.clone(), value);
+                }
+            }
+        }
+
+        windows.insert("event_data".to_string(), JsonValue::Object(record.event_data.clone()));
+        data.insert("event_category".to_string(), json!(rule.event_category));
+        for (section, fields) in sections {
+            data.insert(section.to_string(), JsonValue::Object(fields));
+        }
+        data.insert("features".to_string(), json!({ "event_type": rule.event_type }));
+        data.insert("windows".to_string(), JsonValue::Object(windows));
+        WindowsMappedEvent {
+            rule: Some(rule.name.clone()),
+            event_category: rule.event_category.clone(),
+            event_type: rule.event_type.clone(),
+            data: JsonValue::Object(data),
+            rejected,
+        }
+    }
+}
+
+/// EventData value unless null or one of Windows' empty placeholders ("-", "")
+fn present(value: Option<&JsonValue>) -> Option<&JsonValue> {
+    let value = value?;
+    match value {
+        JsonValue::Null => None,
+        JsonValue::String(s) if s.trim().is_empty() || s.trim() == "-" => None,
+        _ => Some(value),
+    }
+}
+
+fn truncate_text(s: &str) -> String {
+    if s.len() <= MAX_TEXT_LEN {
+        return s.to_string();
+    }
+    let mut end = MAX_TEXT_LEN;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    s[..end].to_string()
+}
+
+/// All known mappings by envelope schema version
+#[derive(Debug, Clone)]
+pub struct WindowsEventMappings {
+    by_version: BTreeMap<u32, WindowsEventMapping>,
+}
+
+impl WindowsEventMappings {
+    /// Mappings shipped with this build (protocol/windows_event_mapping_v*.json)
+    pub fn builtin() -> Result<Self, String> {
+        let mut by_version = BTreeMap::new();
+        for json in BUILTIN_MAPPINGS {
+            let mapping = WindowsEventMapping::from_json(json)?;
+            by_version.insert(mapping.schema_version, mapping);
+        }
+        Ok(Self { by_version })
+    }
+
+    /// Mapping for an envelope's schema_version (absent = 1)
+    pub fn for_version(&self, schema_version: Option<u32>) -> Option<&WindowsEventMapping> {
+        self.by_version.get(&schema_version.unwrap_or(1))
+    }
+
+    pub fn versions(&self) -> Vec<u32> {
+        self.by_version.keys().copied().collect()
+    }
+}
@@ -0,0 +1,183 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/residency.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Data residency routing - region tagging of agents at enrollment, cross-region delivery policy for this ingest instance and the residency classification recorded on raw_events
+
+/*
+ * Data Residency
+ *
+ * Every ingest instance of a multi-region deployment declares its region
+ * (RANSOMEYE_INGEST_REGION). Agents are tagged with a region at enrollment (EnrollRequest.region,
+ * defaulting to the enrolling instance's region) in agents.residency_region; the tag cannot be
+ * changed by re-enrolling.
+ *
+ * An event is accepted when the agent's region equals the instance region, or when the policy
+ * explicitly allows that route (RANSOMEYE_RESIDENCY_ALLOWED_ROUTES="<from>><to>,..." - directed,
+ * no wildcards). Anything else is refused with 421 Misdirected Request and audited
+ * (INGEST_RESIDENCY_REJECT); the agent must deliver to an instance in its own region.
+ *
+ * Agents enrolled before residency existed, and every agent when token auth is disabled (the
+ * region comes from the bearer token's agent row), have no region. RANSOMEYE_RESIDENCY_UNTAGGED
+ * decides:
+ *   local   (default) treat them as belonging to this instance's region
+ *   reject  refuse their events until they are re-enrolled with a region
+ *
+ * Accepted events record the region their data belongs to (the agent's region) in
+ * raw_events.residency_region; exports and reports carry it as their residency classification.
+ * Without RANSOMEYE_INGEST_REGION residency is not enforced and only tagged agents' events are
+ * classified.
+ */
+
+use tokio_postgres::Client;
+use uuid::Uuid;
+
+/// Region identifiers: lowercase letters, digits and '-', starting with a letter (eu-west-1).
+pub const MAX_REGION_LEN: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UntaggedAgents {
+    /// Untagged agents belong to this instance's region
+    Local,
+    /// Untagged agents are refused
+    Reject,
+}
+
+/// Directed cross-region route the policy allows (data of `from` may be delivered in `to`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResidencyRoute {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ResidencyPolicy {
+    /// Region of this ingest instance; `None` disables enforcement
+    pub local_region: Option<String>,
+    pub allowed_routes: Vec<ResidencyRoute>,
+    pub untagged: UntaggedAgents,
+}
+
+/// Outcome for one event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResidencyDecision {
+    /// Accepted; `region` is the residency classification of the event's data (None: unclassified)
+    Accepted { region: Option<String>, cross_region: bool },
+    /// Refused (421); the reason is logged and audited
+    Refused { agent_region: Option<String>, reason: String },
+}
+
+pub fn validate_region(region: &str) -> Result<(), String> {
+    let mut chars = region.chars();
+    let valid = region.len() <= MAX_REGION_LEN
+        && chars.next().is_some_and(|c| c.is_ascii_lowercase())
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if !valid {
+        return Err(format!(
+            "invalid region '{}' (lowercase letters, digits and '-', starting with a letter, at most {} chars)",
+            region, MAX_REGION_LEN
+        ));
+    }
+    Ok(())
+}
+
+impl ResidencyPolicy {
+    /// No region: every event is accepted (single-region deployments and lab mode).
+    pub fn disabled() -> Self {
+        Self { local_region: None, allowed_routes: Vec::new(), untagged: UntaggedAgents::Local }
+    }
+
+    pub fn from_env() -> Result<Self, String> {
+        let local_region = match std::env::var("RANSOMEYE_INGEST_REGION") {
+            Ok(v) if !v.trim().is_empty() => {
+                let region = v.trim().to_string();
+                validate_region(&region).map_err(|e| format!("RANSOMEYE_INGEST_REGION: {}", e))?;
+                Some(region)
+            }
+            _ => None,
+        };
+        let allowed_routes = parse_routes(&std::env::var("RANSOMEYE_RESIDENCY_ALLOWED_ROUTES").unwrap_or_default())?;
+        let untagged = match std::env::var("RANSOMEYE_RESIDENCY_UNTAGGED").as_deref() {
+            Ok("local") | Err(_) => UntaggedAgents::Local,
+            Ok("reject") => UntaggedAgents::Reject,
+            Ok(other) => {
+                return Err(format!("Invalid RANSOMEYE_RESIDENCY_UNTAGGED '{}' (expected local|reject)", other));
+            }
+        };
+        if local_region.is_none() && !allowed_routes.is_empty() {
+            return Err("RANSOMEYE_RESIDENCY_ALLOWED_ROUTES requires RANSOMEYE_INGEST_REGION".to_string());
+        }
+        Ok(Self { local_region, allowed_routes, untagged })
+    }
+
+    /// Region an enrolling agent is tagged with: the requested one, else this instance's.
+    pub fn enrollment_region(&self, requested: Option<&str>) -> Result<Option<String>, String> {
+        match requested.map(str::trim).filter(|r| !r.is_empty()) {
+            Some(region) => {
+                validate_region(region)?;
+                Ok(Some(region.to_string()))
+            }
+            None => Ok(self.local_region.clone()),
+        }
+    }
+
+    pub fn decide(&self, agent_region: Option<&str>) -> ResidencyDecision {
+        let Some(local) = self.local_region.as_deref() else {
+            return ResidencyDecision::Accepted { region: agent_region.map(str::to_string), cross_region: false };
+        };
+        let Some(agent_region) = agent_region else {
+            return match self.untagged {
+                UntaggedAgents::Local => ResidencyDecision::Accepted { region: Some(local.to_string()), cross_region: false },
+                UntaggedAgents::Reject => ResidencyDecision::Refused {
+                    agent_region: None,
+                    reason: format!("agent has no residency region and this instance ({}) rejects untagged agents", local),
+                },
+            };
+        };
+        if agent_region == local {
+            return ResidencyDecision::Accepted { region: Some(local.to_string()), cross_region: false };
+        }
+        if self.allowed_routes.iter().any(|r| r.from == agent_region && r.to == local) {
+            return ResidencyDecision::Accepted { region: Some(agent_region.to_string()), cross_region: true };
+        }
+        ResidencyDecision::Refused {
+            agent_region: Some(agent_region.to_string()),
+            reason: format!("agent region {} may not deliver to region {} (no allowed route)", agent_region, local),
+        }
+    }
+}
+
+fn parse_routes(value: &str) -> Result<Vec<ResidencyRoute>, String> {
+    let mut routes = Vec::new();
+    for item in value.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let (from, to) = item
+            .split_once('>')
+            .ok_or_else(|| format!("Invalid residency route '{}' (expected <from>><to>)", item))?;
+        let (from, to) = (from.trim(), to.trim());
+        validate_region(from).map_err(|e| format!("RANSOMEYE_RESIDENCY_ALLOWED_ROUTES: {}", e))?;
+        validate_region(to).map_err(|e| format!("RANSOMEYE_RESIDENCY_ALLOWED_ROUTES: {}", e))?;
+        if from == to {
+            return Err(format!("Invalid residency route '{}' (same region)", item));
+        }
+        routes.push(ResidencyRoute { from: from.to_string(), to: to.to_string() });
+    }
+    Ok(routes)
+}
+
+/// Tag an agent with its region. Returns the region it is already tagged with when that differs
+/// (the tag is never changed by enrollment).
+pub async fn tag_agent_region(db: &Client, agent_id: Uuid, region: &str) -> Result<Option<String>, tokio_postgres::Error> {
+    let row = db
+        .query_one(
+            r#"
+            WITH tagged AS (
+                UPDATE agents SET residency_region = $2, updated_at = now()
+                WHERE agent_id = $1 AND residency_region IS NULL
+                RETURNING residency_region
+            )
+            SELECT COALESCE((SELECT residency_region FROM tagged), (SELECT residency_region FROM agents WHERE agent_id = $1))
+            "#,
+            &[&agent_id, &region],
+        )
+        .await?;
+    let current: Option<String> = row.get(0);
+    Ok(current.filter(|r| r != region))
+}
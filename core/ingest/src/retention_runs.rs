@@ -0,0 +1,338 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/retention_runs.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Retention run history - the retention_runs row the enforcer writes per run (mode, status, totals, duration, per table stats), its queries and the health assessment that raises failed, overdue and never-run alerts
+
+/*
+ * Retention Runs
+ *
+ * The retention enforcer writes one retention_runs row per run, successful or not, next to the
+ * full audit payload in immutable_audit_log (audit_id links the two). The row carries what is
+ * queried: mode, status, timing, totals and a compact per table summary without batch traces.
+ *
+ * The assessment looks at live runs only; a dry run never satisfies the schedule:
+ *
+ *   failed     the latest live run failed (consecutive_failures counts the streak)
+ *   overdue    the last successful live run is older than max_age_hours
+ *   never_run  no successful live run is recorded
+ *
+ * GET /admin/retention/status and `ransomeye_retention_enforcer --status` both use it.
+ */
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use tokio_postgres::types::Json;
+use tokio_postgres::{Client, Row};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// The enforcer runs hourly; three missed runs in a row raise the overdue alert.
+pub const DEFAULT_MAX_AGE_HOURS: i64 = 3;
+pub const MAX_MAX_AGE_HOURS: i64 = 24 * 30;
+/// Longest error text kept on a failed run
+const MAX_ERROR_BYTES: usize = 4096;
+
+const RUN_COLUMNS: &str = "run_id, mode, status, started_at, ended_at, duration_ms, tables, rows_would_purge, \
+     rows_deleted, held_rows_skipped, referenced_rows_skipped, orphan_rows_deleted, orphan_rows_marked, \
+     lock_timeouts, audit_id, error, table_stats";
+
+/// retention_runs.mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RunMode {
+    DryRun,
+    Live,
+}
+
+impl RunMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RunMode::DryRun => "dry_run",
+            RunMode::Live => "live",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "dry_run" => Some(RunMode::DryRun),
+            "live" => Some(RunMode::Live),
+            _ => None,
+        }
+    }
+}
+
+/// retention_runs.status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    Succeeded,
+    Failed,
+}
+
+impl RunStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RunStatus::Succeeded => "succeeded",
+            RunStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "succeeded" => Some(RunStatus::Succeeded),
+            "failed" => Some(RunStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// One retention_runs row.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RetentionRun {
+    pub run_id: Uuid,
+    pub mode: RunMode,
+    pub status: RunStatus,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub duration_ms: i64,
+    /// Tables with an enabled retention policy
+    pub tables: i64,
+    /// Rows past their cutoff when the run looked, held and referenced rows included
+    pub rows_would_purge: i64,
+    pub rows_deleted: i64,
+    /// Rows kept for an active legal hold
+    pub held_rows_skipped: i64,
+    /// Rows kept because a row outside the purge still references them
+    pub referenced_rows_skipped: i64,
+    pub orphan_rows_deleted: i64,
+    pub orphan_rows_marked: i64,
+    pub lock_timeouts: i64,
+    /// Audit entry with the full run payload (None when the run failed)
+    pub audit_id: Option<Uuid>,
+    pub error: Option<String>,
+    /// Per table summary: table, eligible, rows_would_purge, deleted_rows, batches_executed,
+    /// held_rows_skipped, referenced_rows_skipped, lock_timeouts
+    #[schema(value_type = Vec<Object>)]
+    pub table_stats: JsonValue,
+}
+
+impl RetentionRun {
+    /// A run that ended with `error` before producing totals.
+    pub fn failed(run_id: Uuid, mode: RunMode, started_at: DateTime<Utc>, ended_at: DateTime<Utc>, error: &str) -> Self {
+        let mut end = error.len().min(MAX_ERROR_BYTES);
+        while !error.is_char_boundary(end) {
+            end -= 1;
+        }
+        Self {
+            run_id,
+            mode,
+            status: RunStatus::Failed,
+            started_at,
+            ended_at,
+            duration_ms: (ended_at - started_at).num_milliseconds().max(0),
+            tables: 0,
+            rows_would_purge: 0,
+            rows_deleted: 0,
+            held_rows_skipped: 0,
+            referenced_rows_skipped: 0,
+            orphan_rows_deleted: 0,
+            orphan_rows_marked: 0,
+            lock_timeouts: 0,
+            audit_id: None,
+            error: Some(error[..end].to_string()),
+            table_stats: JsonValue::Array(Vec::new()),
+        }
+    }
+}
+
+/// Inserts the row of a finished run.
+pub async fn record(db: &Client, run: &RetentionRun) -> Result<(), String> {
+    db.execute(
+        &format!(
+            "INSERT INTO retention_runs ({}) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)",
+            RUN_COLUMNS
+        ),
+        &[
+            &run.run_id,
+            &run.mode.as_str(),
+            &run.status.as_str(),
+            &run.started_at,
+            &run.ended_at,
+            &run.duration_ms,
+            &run.tables,
+            &run.rows_would_purge,
+            &run.rows_deleted,
+            &run.held_rows_skipped,
+            &run.referenced_rows_skipped,
+            &run.orphan_rows_deleted,
+            &run.orphan_rows_marked,
+            &run.lock_timeouts,
+            &run.audit_id,
+            &run.error,
+            &Json(&run.table_stats),
+        ],
+    )
+    .await
+    .map(|_| ())
+    .map_err(|e| format!("Failed to record retention run {}: {}", run.run_id, e))
+}
+
+/// The `limit` most recent runs of either mode, newest first.
+pub async fn recent(db: &Client, limit: i64) -> Result<Vec<RetentionRun>, String> {
+    let rows = db
+        .query(&format!("SELECT {} FROM retention_runs ORDER BY started_at DESC LIMIT $1", RUN_COLUMNS), &[&limit])
+        .await
+        .map_err(|e| format!("Failed to read retention runs: {}", e))?;
+    rows.iter().map(run_from_row).collect()
+}
+
+/// Live runs since the last successful one (that one included), newest first, and the last
+/// successful live run; what `assess` needs without reading the whole history.
+pub async fn live_since_last_success(db: &Client) -> Result<(Vec<RetentionRun>, Option<RetentionRun>), String> {
+    let rows = db
+        .query(
+            &format!(
+                r#"
+                SELECT {} FROM retention_runs
+                WHERE mode = 'live'
+                  AND started_at >= COALESCE(
+                      (SELECT max(started_at) FROM retention_runs WHERE mode = 'live' AND status = 'succeeded'),
+                      '-infinity')
+                ORDER BY started_at DESC
+                "#,
+                RUN_COLUMNS
+            ),
+            &[],
+        )
+        .await
+        .map_err(|e| format!("Failed to read retention runs: {}", e))?;
+    let runs = rows.iter().map(run_from_row).collect::<Result<Vec<_>, _>>()?;
+    let last_success = runs.iter().find(|r| r.status == RunStatus::Succeeded).cloned();
+    Ok((runs, last_success))
+}
+
+fn run_from_row(r: &Row) -> Result<RetentionRun, String> {
+    let mode: String = r.get(1);
+    let status: String = r.get(2);
+    let Json(table_stats): Json<JsonValue> = r.get(16);
+    Ok(RetentionRun {
+        run_id: r.get(0),
+        mode: RunMode::parse(&mode).ok_or_else(|| format!("retention_runs has unknown mode '{}'", mode))?,
+        status: RunStatus::parse(&status).ok_or_else(|| format!("retention_runs has unknown status '{}'", status))?,
+        started_at: r.get(3),
+        ended_at: r.get(4),
+        duration_ms: r.get(5),
+        tables: r.get(6),
+        rows_would_purge: r.get(7),
+        rows_deleted: r.get(8),
+        held_rows_skipped: r.get(9),
+        referenced_rows_skipped: r.get(10),
+        orphan_rows_deleted: r.get(11),
+        orphan_rows_marked: r.get(12),
+        lock_timeouts: r.get(13),
+        audit_id: r.get(14),
+        error: r.get(15),
+        table_stats,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RetentionAlertKind {
+    Failed,
+    Overdue,
+    NeverRun,
+}
+
+impl RetentionAlertKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RetentionAlertKind::Failed => "failed",
+            RetentionAlertKind::Overdue => "overdue",
+            RetentionAlertKind::NeverRun => "never_run",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RetentionAlert {
+    pub kind: RetentionAlertKind,
+    pub message: String,
+    /// The failed run, or the last successful one for overdue
+    pub run_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RetentionStatus {
+    pub generated_at: DateTime<Utc>,
+    pub max_age_hours: i64,
+    /// No alerts
+    pub healthy: bool,
+    /// Latest live run
+    pub last_run: Option<RetentionRun>,
+    /// Latest successful live run
+    pub last_success: Option<RetentionRun>,
+    /// Live runs that failed since the last successful one
+    pub consecutive_failures: i64,
+    pub alerts: Vec<RetentionAlert>,
+}
+
+pub fn check_max_age_hours(hours: i64) -> Result<i64, String> {
+    if (1..=MAX_MAX_AGE_HOURS).contains(&hours) {
+        Ok(hours)
+    } else {
+        Err(format!("max_age_hours must be 1-{}", MAX_MAX_AGE_HOURS))
+    }
+}
+
+/// Schedule health from the live runs since the last success (newest first, other modes are
+/// ignored) and that success.
+pub fn assess(
+    recent_live: &[RetentionRun],
+    last_success: Option<RetentionRun>,
+    max_age_hours: i64,
+    now: DateTime<Utc>,
+) -> RetentionStatus {
+    let live: Vec<&RetentionRun> = recent_live.iter().filter(|r| r.mode == RunMode::Live).collect();
+    let consecutive_failures = live.iter().take_while(|r| r.status == RunStatus::Failed).count() as i64;
+    let mut alerts = Vec::new();
+    if let Some(run) = live.first().filter(|r| r.status == RunStatus::Failed) {
+        alerts.push(RetentionAlert {
+            kind: RetentionAlertKind::Failed,
+            message: format!(
+                "live run failed at {} ({} consecutive): {}",
+                run.ended_at.to_rfc3339(),
+                consecutive_failures,
+                run.error.as_deref().unwrap_or("no error recorded")
+            ),
+            run_id: Some(run.run_id),
+        });
+    }
+    match &last_success {
+        Some(run) if run.ended_at < now - chrono::Duration::hours(max_age_hours) => alerts.push(RetentionAlert {
+            kind: RetentionAlertKind::Overdue,
+            message: format!(
+                "no successful live run for {} hours (last {}, limit {} hours)",
+                (now - run.ended_at).num_hours(),
+                run.ended_at.to_rfc3339(),
+                max_age_hours
+            ),
+            run_id: Some(run.run_id),
+        }),
+        Some(_) => {}
+        None => alerts.push(RetentionAlert {
+            kind: RetentionAlertKind::NeverRun,
+            message: "no successful live run recorded".to_string(),
+            run_id: None,
+        }),
+    }
+    RetentionStatus {
+        generated_at: now,
+        max_age_hours,
+        healthy: alerts.is_empty(),
+        last_run: live.first().map(|r| (*r).clone()),
+        last_success,
+        consecutive_failures,
+        alerts,
+    }
+}
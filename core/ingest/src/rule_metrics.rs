@@ -0,0 +1,344 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/rule_metrics.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Detection rule metrics - incident dispositions, the periodic rule_metrics refresh (firings, suppression, true/false positives, time-to-triage) and the rule-tuning report flagging noisy and dead rules
+
+/*
+ * Detection Rule Metrics
+ *
+ * A rule is a (detection_engine, detection_name) pair of detection_results. rule_metrics keeps
+ * one row per rule, refreshed every RANSOMEYE_INGEST_RULE_METRICS_REFRESH_SECS over the last
+ * RANSOMEYE_INGEST_RULE_METRICS_WINDOW_DAYS:
+ *
+ *   firings          detections created in the window
+ *   suppressed       of those, detections stored under a suppression rule
+ *   true/false pos.  detections whose incident (artifacts.incident_id) was closed with that
+ *                    disposition (POST /admin/incidents/close) after the detection was created
+ *   triaged          detections an analyst acted on after they were created: the incident was
+ *                    closed, or the detection or its incident was annotated
+ *   median triage    median time from a triaged detection to that first action
+ *
+ * first_fired_at and last_fired_at are kept across refreshes, so a rule that stops firing keeps
+ * its row with zero counts after its detections leave the window or are purged. The refresh is
+ * one upsert; every instance runs it.
+ *
+ * The rule-tuning report (GET /admin/rules/tuning) flags noisy rules (enough firings with a high
+ * false positive or suppression rate) and dead rules (no firing for dead_after_days). A rule that
+ * has never fired has no row and cannot be reported.
+ */
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+const DEFAULT_REFRESH_SECS: u64 = 900;
+const DEFAULT_WINDOW_DAYS: i64 = 30;
+const MAX_WINDOW_DAYS: i64 = 365;
+const MAX_FIELD_BYTES: usize = 256;
+pub const MAX_REASON_BYTES: usize = 4096;
+
+/// incident_closures.disposition
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Disposition {
+    TruePositive,
+    FalsePositive,
+}
+
+impl Disposition {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Disposition::TruePositive => "true_positive",
+            Disposition::FalsePositive => "false_positive",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "true_positive" => Some(Disposition::TruePositive),
+            "false_positive" => Some(Disposition::FalsePositive),
+            _ => None,
+        }
+    }
+}
+
+/// closed_by and reason of an incident close.
+pub fn check_closure(closed_by: &str, reason: &str) -> Result<(), String> {
+    if closed_by.trim().is_empty() {
+        return Err("closed_by is empty".to_string());
+    }
+    if closed_by.len() > MAX_FIELD_BYTES {
+        return Err(format!("closed_by exceeds {} bytes", MAX_FIELD_BYTES));
+    }
+    if reason.trim().is_empty() {
+        return Err("reason is empty".to_string());
+    }
+    if reason.len() > MAX_REASON_BYTES {
+        return Err(format!("reason exceeds {} bytes", MAX_REASON_BYTES));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+pub struct RuleMetricsConfig {
+    pub refresh_interval: Duration,
+    pub window_days: i64,
+}
+
+impl RuleMetricsConfig {
+    pub fn from_env() -> Result<Self, String> {
+        let refresh_secs = match std::env::var("RANSOMEYE_INGEST_RULE_METRICS_REFRESH_SECS") {
+            Ok(v) => v
+                .parse::<u64>()
+                .ok()
+                .filter(|n| *n > 0)
+                .ok_or_else(|| format!("Invalid RANSOMEYE_INGEST_RULE_METRICS_REFRESH_SECS '{}'", v))?,
+            Err(_) => DEFAULT_REFRESH_SECS,
+        };
+        let window_days = match std::env::var("RANSOMEYE_INGEST_RULE_METRICS_WINDOW_DAYS") {
+            Ok(v) => v
+                .parse::<i64>()
+                .ok()
+                .filter(|n| (1..=MAX_WINDOW_DAYS).contains(n))
+                .ok_or_else(|| {
+                    format!("Invalid RANSOMEYE_INGEST_RULE_METRICS_WINDOW_DAYS '{}' (expected 1-{})", v, MAX_WINDOW_DAYS)
+                })?,
+            Err(_) => DEFAULT_WINDOW_DAYS,
+        };
+        Ok(Self { refresh_interval: Duration::from_secs(refresh_secs), window_days })
+    }
+}
+
+/// One rule_metrics row.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RuleMetrics {
+    pub detection_engine: String,
+    pub detection_name: String,
+    pub first_fired_at: DateTime<Utc>,
+    pub last_fired_at: DateTime<Utc>,
+    /// Start of the window the counts cover
+    pub window_start: DateTime<Utc>,
+    pub firings: i64,
+    pub suppressed: i64,
+    pub true_positives: i64,
+    pub false_positives: i64,
+    pub triaged: i64,
+    /// Median seconds from detection to first analyst action (None when nothing was triaged)
+    pub median_triage_secs: Option<f64>,
+    pub refreshed_at: DateTime<Utc>,
+}
+
+impl RuleMetrics {
+    /// Share of firings that were suppressed (0 without firings).
+    pub fn suppression_rate(&self) -> f64 {
+        if self.firings == 0 {
+            0.0
+        } else {
+            self.suppressed as f64 / self.firings as f64
+        }
+    }
+
+    /// Share of dispositioned detections closed as false positive (None without dispositions).
+    pub fn false_positive_rate(&self) -> Option<f64> {
+        let decided = self.true_positives + self.false_positives;
+        (decided > 0).then(|| self.false_positives as f64 / decided as f64)
+    }
+}
+
+/// Thresholds of the tuning report (query parameters of GET /admin/rules/tuning).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TuningThresholds {
+    /// Fewest firings in the window before a rule can be noisy
+    pub noisy_min_firings: i64,
+    /// False positive rate (of dispositioned detections) at which a rule is noisy
+    pub noisy_false_positive_rate: f64,
+    /// Suppression rate at which a rule is noisy
+    pub noisy_suppression_rate: f64,
+    /// Days without a firing after which a rule is dead
+    pub dead_after_days: i64,
+}
+
+impl Default for TuningThresholds {
+    fn default() -> Self {
+        Self { noisy_min_firings: 20, noisy_false_positive_rate: 0.5, noisy_suppression_rate: 0.5, dead_after_days: 30 }
+    }
+}
+
+impl TuningThresholds {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.noisy_min_firings < 1 {
+            return Err("noisy_min_firings must be at least 1".to_string());
+        }
+        for (name, rate) in
+            [("noisy_false_positive_rate", self.noisy_false_positive_rate), ("noisy_suppression_rate", self.noisy_suppression_rate)]
+        {
+            if !(rate > 0.0 && rate <= 1.0) {
+                return Err(format!("{} must be in (0, 1]", name));
+            }
+        }
+        if !(1..=MAX_WINDOW_DAYS * 10).contains(&self.dead_after_days) {
+            return Err(format!("dead_after_days must be 1-{}", MAX_WINDOW_DAYS * 10));
+        }
+        Ok(())
+    }
+}
+
+/// A flagged rule with its derived rates and why it was flagged.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TuningEntry {
+    #[serde(flatten)]
+    pub metrics: RuleMetrics,
+    pub suppression_rate: f64,
+    pub false_positive_rate: Option<f64>,
+    pub reasons: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RuleTuningReport {
+    pub generated_at: DateTime<Utc>,
+    pub thresholds: TuningThresholds,
+    /// Rules with a metrics row
+    pub rules: usize,
+    /// Most false positives first, then most firings
+    pub noisy: Vec<TuningEntry>,
+    /// Longest silent first
+    pub dead: Vec<TuningEntry>,
+}
+
+/// Noisy and dead rules among `rules` (any order).
+pub fn tuning_report(rules: Vec<RuleMetrics>, thresholds: TuningThresholds, now: DateTime<Utc>) -> RuleTuningReport {
+    let total = rules.len();
+    let dead_before = now - chrono::Duration::days(thresholds.dead_after_days);
+    let mut noisy = Vec::new();
+    let mut dead = Vec::new();
+    for rule in rules {
+        let suppression_rate = rule.suppression_rate();
+        let false_positive_rate = rule.false_positive_rate();
+        if rule.last_fired_at < dead_before {
+            let reasons = vec![format!(
+                "no firing since {} ({} days)",
+                rule.last_fired_at.to_rfc3339(),
+                (now - rule.last_fired_at).num_days()
+            )];
+            dead.push(TuningEntry { metrics: rule, suppression_rate, false_positive_rate, reasons });
+            continue;
+        }
+        if rule.firings < thresholds.noisy_min_firings {
+            continue;
+        }
+        let mut reasons = Vec::new();
+        if let Some(rate) = false_positive_rate.filter(|r| *r >= thresholds.noisy_false_positive_rate) {
+            reasons.push(format!(
+                "false positive rate {:.2} ({} of {} dispositioned)",
+                rate,
+                rule.false_positives,
+                rule.true_positives + rule.false_positives
+            ));
+        }
+        if suppression_rate >= thresholds.noisy_suppression_rate {
+            reasons.push(format!("suppression rate {:.2} ({} of {} firings)", suppression_rate, rule.suppressed, rule.firings));
+        }
+        if !reasons.is_empty() {
+            noisy.push(TuningEntry { metrics: rule, suppression_rate, false_positive_rate, reasons });
+        }
+    }
+    noisy.sort_by_key(|e| {
+        (
+            std::cmp::Reverse(e.metrics.false_positives),
+            std::cmp::Reverse(e.metrics.firings),
+            (e.metrics.detection_engine.clone(), e.metrics.detection_name.clone()),
+        )
+    });
+    dead.sort_by_key(|e| {
+        (e.metrics.last_fired_at, (e.metrics.detection_engine.clone(), e.metrics.detection_name.clone()))
+    });
+    RuleTuningReport { generated_at: now, thresholds, rules: total, noisy, dead }
+}
+
+/// Recomputes every rule's row over [window_start, now); returns the rows written.
+pub async fn refresh(db: &Client, window_start: DateTime<Utc>) -> Result<u64, String> {
+    db.execute(
+        r#"
+        WITH fired AS (
+            SELECT d.detection_engine, d.detection_name, d.created_at,
+                   d.suppression_id IS NOT NULL AS suppressed,
+                   c.disposition,
+                   LEAST(c.closed_at, n.annotated_at) AS triaged_at
+            FROM detection_results d
+            LEFT JOIN incident_closures c
+              ON c.incident_id::text = lower(d.artifacts->>'incident_id') AND c.closed_at >= d.created_at
+            LEFT JOIN LATERAL (
+                SELECT min(a.created_at) AS annotated_at FROM annotations a
+                WHERE a.created_at >= d.created_at
+                  AND ((a.subject_type = 'detection' AND a.subject_id = d.detection_id::text)
+                    OR (a.subject_type = 'incident' AND a.subject_id = lower(d.artifacts->>'incident_id')))
+            ) n ON true
+            WHERE d.created_at >= $1
+        ),
+        windowed AS (
+            SELECT detection_engine, detection_name,
+                   min(created_at) AS first_fired_at,
+                   max(created_at) AS last_fired_at,
+                   count(*) AS firings,
+                   count(*) FILTER (WHERE suppressed) AS suppressed,
+                   count(*) FILTER (WHERE disposition = 'true_positive') AS true_positives,
+                   count(*) FILTER (WHERE disposition = 'false_positive') AS false_positives,
+                   count(triaged_at) AS triaged,
+                   percentile_cont(0.5) WITHIN GROUP (ORDER BY extract(epoch FROM triaged_at - created_at))
+                       AS median_triage_secs
+            FROM fired
+            GROUP BY detection_engine, detection_name
+        ),
+        rules AS (
+            SELECT detection_engine, detection_name FROM windowed
+            UNION
+            SELECT detection_engine, detection_name FROM rule_metrics
+        )
+        INSERT INTO rule_metrics (detection_engine, detection_name, first_fired_at, last_fired_at, window_start,
+                                  firings, suppressed, true_positives, false_positives, triaged,
+                                  median_triage_secs, refreshed_at)
+        SELECT r.detection_engine, r.detection_name,
+               COALESCE(w.first_fired_at, m.first_fired_at), COALESCE(w.last_fired_at, m.last_fired_at), $1,
+               COALESCE(w.firings, 0), COALESCE(w.suppressed, 0),
+               COALESCE(w.true_positives, 0), COALESCE(w.false_positives, 0), COALESCE(w.triaged, 0),
+               w.median_triage_secs, now()
+        FROM rules r
+        LEFT JOIN windowed w USING (detection_engine, detection_name)
+        LEFT JOIN rule_metrics m USING (detection_engine, detection_name)
+        ON CONFLICT (detection_engine, detection_name) DO UPDATE SET
+            first_fired_at = LEAST(rule_metrics.first_fired_at, EXCLUDED.first_fired_at),
+            last_fired_at = GREATEST(rule_metrics.last_fired_at, EXCLUDED.last_fired_at),
+            window_start = EXCLUDED.window_start,
+            firings = EXCLUDED.firings,
+            suppressed = EXCLUDED.suppressed,
+            true_positives = EXCLUDED.true_positives,
+            false_positives = EXCLUDED.false_positives,
+            triaged = EXCLUDED.triaged,
+            median_triage_secs = EXCLUDED.median_triage_secs,
+            refreshed_at = EXCLUDED.refreshed_at
+        "#,
+        &[&window_start],
+    )
+    .await
+    .map_err(|e| format!("Failed to refresh rule metrics: {}", e))
+}
+
+/// Periodic refresh, first run at startup; every instance runs it, the upsert is idempotent.
+pub fn spawn_refresh(db: Arc<Client>, cfg: &RuleMetricsConfig) -> tokio::task::JoinHandle<()> {
+    let interval = cfg.refresh_interval;
+    let window = chrono::Duration::days(cfg.window_days);
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(interval);
+        loop {
+            tick.tick().await;
+            match refresh(&db, Utc::now() - window).await {
+                Ok(rules) => info!("Rule metrics refreshed for {} rule(s)", rules),
+                Err(e) => warn!("Rule metrics refresh failed: {}", e),
+            }
+        }
+    })
+}
@@ -0,0 +1,210 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/runtime_controls.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Runtime-adjustable log filter and per-event diagnostic sampling ratio with TTL-bounded overrides that revert to the startup baseline
+
+/*
+ * Runtime Controls
+ *
+ * Every override carries a TTL (clamped to RANSOMEYE_RUNTIME_OVERRIDE_MAX_TTL_SECS) and a
+ * generation number. Reverting only succeeds for the generation that scheduled it, so a newer
+ * override is never undone by an older timer. Sampling governs per-event diagnostic logging
+ * only; it never drops telemetry.
+ */
+
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use utoipa::ToSchema;
+use tracing_subscriber::filter::Targets;
+
+use kernel::otel::LogFilterHandle;
+
+const PPM: u64 = 1_000_000;
+
+#[derive(Debug, Clone)]
+pub struct RuntimeControlConfig {
+    pub default_ttl_secs: u64,
+    pub max_ttl_secs: u64,
+    /// Filter applied by SIGUSR1.
+    pub signal_log_filter: String,
+    /// Startup fraction of events whose per-event diagnostics are logged (0.0..=1.0).
+    pub baseline_sample_ratio: f64,
+}
+
+impl RuntimeControlConfig {
+    pub fn from_env() -> Result<Self, String> {
+        let default_ttl_secs = env_u64("RANSOMEYE_RUNTIME_OVERRIDE_TTL_SECS", 900)?;
+        let max_ttl_secs = env_u64("RANSOMEYE_RUNTIME_OVERRIDE_MAX_TTL_SECS", 4 * 3600)?;
+        if default_ttl_secs == 0 || default_ttl_secs > max_ttl_secs {
+            return Err(format!(
+                "RANSOMEYE_RUNTIME_OVERRIDE_TTL_SECS must be in 1..={} (got {})",
+                max_ttl_secs, default_ttl_secs
+            ));
+        }
+        let signal_log_filter = std::env::var("RANSOMEYE_SIGUSR1_LOG_FILTER").unwrap_or_else(|_| "debug".to_string());
+        signal_log_filter
+            .parse::<Targets>()
+            .map_err(|e| format!("Invalid RANSOMEYE_SIGUSR1_LOG_FILTER '{}': {}", signal_log_filter, e))?;
+        let baseline_sample_ratio = match std::env::var("RANSOMEYE_EVENT_LOG_SAMPLE_RATIO") {
+            Ok(v) => v
+                .parse::<f64>()
+                .map_err(|e| format!("Invalid RANSOMEYE_EVENT_LOG_SAMPLE_RATIO='{}': {}", v, e))?,
+            Err(_) => 1.0,
+        };
+        ratio_to_ppm(baseline_sample_ratio)?;
+        Ok(Self {
+            default_ttl_secs,
+            max_ttl_secs,
+            signal_log_filter,
+            baseline_sample_ratio,
+        })
+    }
+}
+
+/// Requested change; absent fields are left as they are.
+#[derive(Debug, Clone, Default)]
+pub struct RuntimeChange {
+    pub log_filter: Option<String>,
+    pub sample_ratio: Option<f64>,
+    pub ttl_secs: Option<u64>,
+}
+
+/// Snapshot of the effective settings (returned by the admin endpoint and written to audit).
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct RuntimeState {
+    pub log_filter: String,
+    pub sample_ratio: f64,
+    pub generation: u64,
+    /// Seconds until automatic revert; None when running on the baseline.
+    pub ttl_secs: Option<u64>,
+}
+
+pub struct RuntimeControls {
+    log_filter: Option<LogFilterHandle>,
+    baseline_filter: String,
+    baseline_sample_ppm: u32,
+    cfg: RuntimeControlConfig,
+    current_filter: Mutex<String>,
+    current_ttl: Mutex<Option<u64>>,
+    sample_ppm: AtomicU32,
+    sample_counter: AtomicU64,
+    generation: AtomicU64,
+}
+
+impl RuntimeControls {
+    /// `log_filter` is None when the process has no reloadable subscriber (sampling still works).
+    pub fn new(log_filter: Option<LogFilterHandle>, baseline_filter: &str, cfg: RuntimeControlConfig) -> Result<Self, String> {
+        let baseline_sample_ppm = ratio_to_ppm(cfg.baseline_sample_ratio)?;
+        Ok(Self {
+            log_filter,
+            baseline_filter: baseline_filter.to_string(),
+            baseline_sample_ppm,
+            cfg,
+            current_filter: Mutex::new(baseline_filter.to_string()),
+            current_ttl: Mutex::new(None),
+            sample_ppm: AtomicU32::new(baseline_sample_ppm),
+            sample_counter: AtomicU64::new(0),
+            generation: AtomicU64::new(0),
+        })
+    }
+
+    pub fn config(&self) -> &RuntimeControlConfig {
+        &self.cfg
+    }
+
+    pub fn state(&self) -> RuntimeState {
+        RuntimeState {
+            log_filter: self.current_filter.lock().clone(),
+            sample_ratio: self.sample_ppm.load(Ordering::Relaxed) as f64 / PPM as f64,
+            generation: self.generation.load(Ordering::SeqCst),
+            ttl_secs: *self.current_ttl.lock(),
+        }
+    }
+
+    /// Validate and apply an override. Returns the new state; the caller schedules
+    /// `revert_if(state.generation)` after `state.ttl_secs`.
+    pub fn apply(&self, change: &RuntimeChange) -> Result<RuntimeState, String> {
+        if change.log_filter.is_none() && change.sample_ratio.is_none() {
+            return Err("No change requested (expected log_filter and/or sample_ratio)".to_string());
+        }
+        let ttl = change.ttl_secs.unwrap_or(self.cfg.default_ttl_secs);
+        if ttl == 0 || ttl > self.cfg.max_ttl_secs {
+            return Err(format!("ttl_secs must be in 1..={}", self.cfg.max_ttl_secs));
+        }
+        let targets = match &change.log_filter {
+            Some(f) => Some(f.parse::<Targets>().map_err(|e| format!("Invalid log_filter '{}': {}", f, e))?),
+            None => None,
+        };
+        let ppm = match change.sample_ratio {
+            Some(r) => Some(ratio_to_ppm(r)?),
+            None => None,
+        };
+
+        let mut current_filter = self.current_filter.lock();
+        if let (Some(targets), Some(f)) = (targets, &change.log_filter) {
+            self.reload(targets)?;
+            *current_filter = f.clone();
+        }
+        if let Some(ppm) = ppm {
+            self.sample_ppm.store(ppm, Ordering::Relaxed);
+        }
+        *self.current_ttl.lock() = Some(ttl);
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        drop(current_filter);
+        Ok(self.state())
+    }
+
+    /// Restore the baseline if `generation` is still the active override. Returns the restored state.
+    pub fn revert_if(&self, generation: u64) -> Result<Option<RuntimeState>, String> {
+        let mut current_filter = self.current_filter.lock();
+        if self.generation.load(Ordering::SeqCst) != generation || self.current_ttl.lock().is_none() {
+            return Ok(None);
+        }
+        let targets = self
+            .baseline_filter
+            .parse::<Targets>()
+            .map_err(|e| format!("Baseline log filter invalid: {}", e))?;
+        self.reload(targets)?;
+        *current_filter = self.baseline_filter.clone();
+        self.sample_ppm.store(self.baseline_sample_ppm, Ordering::Relaxed);
+        *self.current_ttl.lock() = None;
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        drop(current_filter);
+        Ok(Some(self.state()))
+    }
+
+    /// Evenly spread sampling decision for per-event diagnostics (exact ratio over any window).
+    pub fn should_sample_event(&self) -> bool {
+        let ppm = self.sample_ppm.load(Ordering::Relaxed) as u64;
+        if ppm == 0 {
+            return false;
+        }
+        if ppm >= PPM {
+            return true;
+        }
+        let n = self.sample_counter.fetch_add(1, Ordering::Relaxed) % PPM;
+        (n + 1) * ppm / PPM > n * ppm / PPM
+    }
+
+    fn reload(&self, targets: Targets) -> Result<(), String> {
+        match &self.log_filter {
+            Some(handle) => handle.reload(targets).map_err(|e| format!("Log filter reload failed: {}", e)),
+            None => Err("Log filter is not reloadable in this process".to_string()),
+        }
+    }
+}
+
+fn ratio_to_ppm(ratio: f64) -> Result<u32, String> {
+    if !ratio.is_finite() || !(0.0..=1.0).contains(&ratio) {
+        return Err(format!("sample_ratio must be within 0.0..=1.0 (got {})", ratio));
+    }
+    Ok((ratio * PPM as f64).round() as u32)
+}
+
+fn env_u64(key: &str, default_value: u64) -> Result<u64, String> {
+    match std::env::var(key) {
+        Ok(v) => v.parse::<u64>().map_err(|e| format!("Invalid {}='{}': {}", key, v, e)),
+        Err(_) => Ok(default_value),
+    }
+}
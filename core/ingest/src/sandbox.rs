@@ -0,0 +1,966 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/sandbox.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Sandboxed detonation - policy-gated submission of suspicious binaries (hash or sample) to a Cuckoo-compatible sandbox, verdict polling, report spooling for the evidence store and detections for malicious verdicts
+
+/*
+ * Sandboxed Detonation
+ *
+ * Agents submit binaries they find suspicious (POST /agents/sandbox/samples) and operators submit
+ * hashes (POST /admin/sandbox-submissions). Each submission is a sandbox_submissions row (status
+ * queued). What may leave the deployment is ingest policy:
+ *
+ *   sandbox        RANSOMEYE_INGEST_SANDBOX_URL (unset: off), bearer token from
+ *                  RANSOMEYE_INGEST_SANDBOX_API_TOKEN_PATH
+ *   submit         RANSOMEYE_INGEST_SANDBOX_SUBMIT: hash (default; the file never leaves, the
+ *                  sandbox is asked for an analysis it already holds) or sample (agents may
+ *                  upload the file, up to RANSOMEYE_INGEST_SANDBOX_MAX_SAMPLE_BYTES)
+ *   verdict        RANSOMEYE_INGEST_SANDBOX_MALICIOUS_SCORE / _SUSPICIOUS_SCORE on the sandbox's
+ *                  0-10 score
+ *
+ * One submission per SHA-256 is open or reported at a time; a repeat gets the existing one.
+ * A hash the sandbox did not know (not_found) is only looked up again after a day, or at once
+ * when an agent brings the sample.
+ *
+ * The worker polls the sandbox every RANSOMEYE_INGEST_SANDBOX_POLL_SECS over the Cuckoo REST API:
+ *
+ *   sample         POST /tasks/create/file (multipart "file")
+ *   hash           GET /files/view/sha256/<sha256>, then GET /tasks/sample/<sample id>: the
+ *                  latest task of the sample
+ *   verdict        GET /tasks/view/<task id> until status reported, then GET /tasks/report/<id>
+ *                  (info.score, or malscore for CAPE)
+ *
+ * The raw report is spooled as <spool>/<submission_id>.report with a manifest
+ * <submission_id>.json for `reporting import-sandbox-reports`. A malicious verdict raises a
+ * critical sandbox_malicious_verdict detection in the same transaction that records the verdict;
+ * its artifacts name the agent, so the detection also goes through suppression, webhooks and
+ * triggered packet capture. Submissions without a verdict after RANSOMEYE_INGEST_SANDBOX_TIMEOUT_SECS
+ * fail. Needs the Postgres control plane.
+ */
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use crypto::digest::Sha256;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use tokio_postgres::{Client, Row};
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::dashboard_cache::DashboardCache;
+use crate::storage::{AuditRecord, DetectionRecord, TelemetryStore};
+
+/// Detection raised for a malicious verdict
+pub const DETECTION_NAME: &str = "sandbox_malicious_verdict";
+pub const DETECTION_ENGINE: &str = "ingest_sandbox";
+/// Submission statuses (sandbox_submissions_status_chk)
+pub const SUBMISSION_STATUSES: &[&str] = &["queued", "submitted", "reported", "not_found", "failed"];
+/// Spool subdirectory for uploaded samples awaiting submission
+pub const SAMPLES_DIR: &str = "samples";
+/// Longest file_path, reason or submitter accepted
+pub const MAX_TEXT_BYTES: usize = 1024;
+/// A not_found hash is looked up again after this long
+pub const NOT_FOUND_RETRY_SECS: i64 = 86_400;
+/// Submissions and polls per worker pass
+const BATCH: i64 = 16;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+const DEFAULT_MAX_SAMPLE_BYTES: u64 = 32 * 1024 * 1024;
+const MAX_MAX_SAMPLE_BYTES: u64 = 256 * 1024 * 1024;
+const DEFAULT_MAX_REPORT_BYTES: u64 = 64 * 1024 * 1024;
+const MAX_MAX_REPORT_BYTES: u64 = 1024 * 1024 * 1024;
+const DEFAULT_POLL_SECS: u64 = 30;
+const DEFAULT_TIMEOUT_SECS: u64 = 3600;
+const DEFAULT_MALICIOUS_SCORE: f64 = 7.0;
+const DEFAULT_SUSPICIOUS_SCORE: f64 = 4.0;
+const DEFAULT_SPOOL_DIR: &str = "/var/lib/ransomeye/ingest/sandbox-spool";
+
+fn env_bounded(key: &str, default_value: u64, min: u64, max: u64) -> Result<u64, String> {
+    match std::env::var(key) {
+        Ok(v) => v
+            .parse::<u64>()
+            .ok()
+            .filter(|n| (min..=max).contains(n))
+            .ok_or_else(|| format!("Invalid {} '{}' (expected {}..={})", key, v, min, max)),
+        Err(_) => Ok(default_value),
+    }
+}
+
+fn env_score(key: &str, default_value: f64) -> Result<f64, String> {
+    match std::env::var(key) {
+        Ok(v) => v
+            .parse::<f64>()
+            .ok()
+            .filter(|n| *n > 0.0 && *n <= 10.0)
+            .ok_or_else(|| format!("Invalid {} '{}' (expected a score in (0, 10])", key, v)),
+        Err(_) => Ok(default_value),
+    }
+}
+
+/// What a submission sends to the sandbox
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubmitMode {
+    /// Only the SHA-256 (the sandbox must already hold the file)
+    Hash,
+    /// The file itself, detonated in the sandbox
+    Sample,
+}
+
+impl SubmitMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SubmitMode::Hash => "hash",
+            SubmitMode::Sample => "sample",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "hash" => Some(SubmitMode::Hash),
+            "sample" => Some(SubmitMode::Sample),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verdict {
+    Clean,
+    Suspicious,
+    Malicious,
+}
+
+impl Verdict {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Verdict::Clean => "clean",
+            Verdict::Suspicious => "suspicious",
+            Verdict::Malicious => "malicious",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct SandboxConfig {
+    /// Base URL of the Cuckoo REST API (e.g. http://sandbox:8090)
+    pub api_url: String,
+    pub api_token: Option<String>,
+    pub submit: SubmitMode,
+    pub max_sample_bytes: u64,
+    pub max_report_bytes: u64,
+    pub poll_interval: Duration,
+    pub timeout_secs: u64,
+    pub malicious_score: f64,
+    pub suspicious_score: f64,
+    /// Samples awaiting submission, and reports with their manifests until
+    /// `reporting import-sandbox-reports` takes them
+    pub spool_dir: PathBuf,
+}
+
+impl std::fmt::Debug for SandboxConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SandboxConfig")
+            .field("api_url", &self.api_url)
+            .field("api_token", &self.api_token.as_ref().map(|_| "<redacted>"))
+            .field("submit", &self.submit)
+            .field("max_sample_bytes", &self.max_sample_bytes)
+            .field("max_report_bytes", &self.max_report_bytes)
+            .field("poll_interval", &self.poll_interval)
+            .field("timeout_secs", &self.timeout_secs)
+            .field("malicious_score", &self.malicious_score)
+            .field("suspicious_score", &self.suspicious_score)
+            .field("spool_dir", &self.spool_dir)
+            .finish()
+    }
+}
+
+impl SandboxConfig {
+    /// RANSOMEYE_INGEST_SANDBOX_URL enables submissions (http or https, default off);
+    /// RANSOMEYE_INGEST_SANDBOX_API_TOKEN_PATH (file with the sandbox API bearer token),
+    /// RANSOMEYE_INGEST_SANDBOX_SUBMIT (hash or sample, default hash),
+    /// RANSOMEYE_INGEST_SANDBOX_MAX_SAMPLE_BYTES (default 32 MiB, at most 256 MiB),
+    /// RANSOMEYE_INGEST_SANDBOX_MAX_REPORT_BYTES (default 64 MiB, at most 1 GiB),
+    /// RANSOMEYE_INGEST_SANDBOX_POLL_SECS (default 30), RANSOMEYE_INGEST_SANDBOX_TIMEOUT_SECS
+    /// (default 3600), RANSOMEYE_INGEST_SANDBOX_MALICIOUS_SCORE (default 7.0),
+    /// RANSOMEYE_INGEST_SANDBOX_SUSPICIOUS_SCORE (default 4.0, below the malicious score),
+    /// RANSOMEYE_INGEST_SANDBOX_SPOOL_DIR (default /var/lib/ransomeye/ingest/sandbox-spool).
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let api_url = match std::env::var("RANSOMEYE_INGEST_SANDBOX_URL") {
+            Ok(url) if !url.trim().is_empty() => url.trim().trim_end_matches('/').to_string(),
+            _ => return Ok(None),
+        };
+        let parsed = url::Url::parse(&api_url).map_err(|e| format!("Invalid RANSOMEYE_INGEST_SANDBOX_URL '{}': {}", api_url, e))?;
+        if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+            return Err(format!("Invalid RANSOMEYE_INGEST_SANDBOX_URL '{}' (expected http(s)://host[:port])", api_url));
+        }
+        let api_token = match std::env::var("RANSOMEYE_INGEST_SANDBOX_API_TOKEN_PATH") {
+            Ok(path) => {
+                let token = fs::read_to_string(&path)
+                    .map_err(|e| format!("Failed to read RANSOMEYE_INGEST_SANDBOX_API_TOKEN_PATH {}: {}", path, e))?
+                    .trim()
+                    .to_string();
+                if token.is_empty() {
+                    return Err(format!("Sandbox API token file {} is empty", path));
+                }
+                Some(token)
+            }
+            Err(_) => None,
+        };
+        let submit = match std::env::var("RANSOMEYE_INGEST_SANDBOX_SUBMIT") {
+            Ok(v) => SubmitMode::parse(&v)
+                .ok_or_else(|| format!("Invalid RANSOMEYE_INGEST_SANDBOX_SUBMIT '{}' (expected hash or sample)", v))?,
+            Err(_) => SubmitMode::Hash,
+        };
+        let malicious_score = env_score("RANSOMEYE_INGEST_SANDBOX_MALICIOUS_SCORE", DEFAULT_MALICIOUS_SCORE)?;
+        let suspicious_score = env_score("RANSOMEYE_INGEST_SANDBOX_SUSPICIOUS_SCORE", DEFAULT_SUSPICIOUS_SCORE)?;
+        if suspicious_score >= malicious_score {
+            return Err(format!(
+                "RANSOMEYE_INGEST_SANDBOX_SUSPICIOUS_SCORE {} must be below the malicious score {}",
+                suspicious_score, malicious_score
+            ));
+        }
+        Ok(Some(Self {
+            api_url,
+            api_token,
+            submit,
+            max_sample_bytes: env_bounded("RANSOMEYE_INGEST_SANDBOX_MAX_SAMPLE_BYTES", DEFAULT_MAX_SAMPLE_BYTES, 1, MAX_MAX_SAMPLE_BYTES)?,
+            max_report_bytes: env_bounded("RANSOMEYE_INGEST_SANDBOX_MAX_REPORT_BYTES", DEFAULT_MAX_REPORT_BYTES, 1024, MAX_MAX_REPORT_BYTES)?,
+            poll_interval: Duration::from_secs(env_bounded("RANSOMEYE_INGEST_SANDBOX_POLL_SECS", DEFAULT_POLL_SECS, 1, 3600)?),
+            timeout_secs: env_bounded("RANSOMEYE_INGEST_SANDBOX_TIMEOUT_SECS", DEFAULT_TIMEOUT_SECS, 60, 7 * 86_400)?,
+            malicious_score,
+            suspicious_score,
+            spool_dir: PathBuf::from(
+                std::env::var("RANSOMEYE_INGEST_SANDBOX_SPOOL_DIR").unwrap_or_else(|_| DEFAULT_SPOOL_DIR.to_string()),
+            ),
+        }))
+    }
+
+    pub fn verdict(&self, score: f64) -> Verdict {
+        if score >= self.malicious_score {
+            Verdict::Malicious
+        } else if score >= self.suspicious_score {
+            Verdict::Suspicious
+        } else {
+            Verdict::Clean
+        }
+    }
+}
+
+pub fn is_sha256_hex(s: &str) -> bool {
+    s.len() == 64 && s.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Spooled sample of a SHA-256 (lowercase hex)
+pub fn sample_path(spool_dir: &Path, sha256: &str) -> PathBuf {
+    spool_dir.join(SAMPLES_DIR).join(sha256)
+}
+
+fn write_atomic(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    let mut file = fs::File::create(&tmp)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)
+}
+
+pub fn write_sample(spool_dir: &Path, sha256: &str, sample: &[u8]) -> std::io::Result<()> {
+    fs::create_dir_all(spool_dir.join(SAMPLES_DIR))?;
+    write_atomic(&sample_path(spool_dir, sha256), sample)
+}
+
+pub fn remove_sample(spool_dir: &Path, sha256: &str) {
+    let _ = fs::remove_file(sample_path(spool_dir, sha256));
+}
+
+/// Sidecar of a spooled sandbox report (<submission_id>.json next to <submission_id>.report);
+/// read by the reporting importer.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpoolManifest {
+    pub submission_id: Uuid,
+    pub sha256: String,
+    pub file_path: Option<String>,
+    pub file_size: Option<i64>,
+    pub agent_id: Option<Uuid>,
+    pub incident_id: Option<Uuid>,
+    /// agent or operator
+    pub source: String,
+    pub submitted_by: String,
+    /// hash or sample
+    pub mode: String,
+    pub sandbox_url: String,
+    pub sandbox_task_id: i64,
+    pub queued_at: DateTime<Utc>,
+    pub reported_at: DateTime<Utc>,
+    pub score: f64,
+    pub verdict: Verdict,
+    /// sandbox_malicious_verdict detection (malicious verdicts only)
+    pub detection_id: Option<Uuid>,
+    pub report_file: String,
+    pub report_sha256: String,
+    pub report_bytes: u64,
+}
+
+/// Write the report, then its manifest: the importer only takes reports whose manifest exists.
+pub fn write_spool(spool_dir: &Path, manifest: &SpoolManifest, report: &[u8]) -> std::io::Result<()> {
+    fs::create_dir_all(spool_dir)?;
+    write_atomic(&spool_dir.join(&manifest.report_file), report)?;
+    let json = serde_json::to_vec_pretty(manifest).map_err(std::io::Error::other)?;
+    write_atomic(&spool_dir.join(format!("{}.json", manifest.submission_id)), &json)
+}
+
+pub fn remove_spool(spool_dir: &Path, submission_id: Uuid) {
+    let _ = fs::remove_file(spool_dir.join(format!("{}.json", submission_id)));
+    let _ = fs::remove_file(spool_dir.join(format!("{}.report", submission_id)));
+}
+
+/// Task id from POST /tasks/create/file (Cuckoo: task_id; CAPE: data.task_ids[0]).
+pub fn parse_task_id(response: &JsonValue) -> Option<i64> {
+    response
+        .get("task_id")
+        .and_then(JsonValue::as_i64)
+        .or_else(|| response.pointer("/data/task_ids/0").and_then(JsonValue::as_i64))
+        .filter(|id| *id > 0)
+}
+
+/// Latest task of a sample from GET /tasks/sample/<id> (tasks[].id).
+pub fn latest_task(response: &JsonValue) -> Option<i64> {
+    response.get("tasks")?.as_array()?.iter().filter_map(|t| t.get("id").and_then(JsonValue::as_i64)).max()
+}
+
+/// Score of a report (Cuckoo: info.score; CAPE: malscore), 0-10.
+pub fn parse_score(report: &JsonValue) -> Option<f64> {
+    report
+        .pointer("/info/score")
+        .and_then(JsonValue::as_f64)
+        .or_else(|| report.get("malscore").and_then(JsonValue::as_f64))
+        .filter(|s| s.is_finite() && *s >= 0.0)
+}
+
+/// Where a sandbox task stands (GET /tasks/view/<id>, task.status)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskState {
+    /// pending, running, completed (processing) or recovered
+    Running,
+    Reported,
+    Failed(String),
+}
+
+pub fn task_state(response: &JsonValue) -> Option<TaskState> {
+    let status = response.pointer("/task/status").and_then(JsonValue::as_str)?;
+    Some(match status {
+        "reported" => TaskState::Reported,
+        s if s.starts_with("failed") => TaskState::Failed(s.to_string()),
+        _ => TaskState::Running,
+    })
+}
+
+/// A submission as listed by the admin API and returned to submitters.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SandboxSubmission {
+    pub submission_id: Uuid,
+    pub sha256: String,
+    pub file_path: Option<String>,
+    pub file_size: Option<i64>,
+    pub agent_id: Option<Uuid>,
+    pub incident_id: Option<Uuid>,
+    /// agent or operator
+    pub source: String,
+    pub submitted_by: String,
+    pub reason: Option<String>,
+    /// hash or sample
+    pub mode: String,
+    /// queued, submitted, reported, not_found or failed
+    pub status: String,
+    pub queued_at: DateTime<Utc>,
+    pub submitted_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub sandbox_task_id: Option<i64>,
+    pub score: Option<f64>,
+    /// clean, suspicious or malicious (reported only)
+    pub verdict: Option<String>,
+    pub report_sha256: Option<String>,
+    pub detection_id: Option<Uuid>,
+    pub failure_reason: Option<String>,
+}
+
+pub const SUBMISSION_COLUMNS: &str = "submission_id, sha256, file_path, file_size, agent_id, incident_id, source, \
+    submitted_by, reason, mode, status, queued_at, submitted_at, finished_at, sandbox_task_id, score, verdict, \
+    report_sha256, detection_id, failure_reason";
+
+pub fn submission_from_row(r: &Row) -> SandboxSubmission {
+    SandboxSubmission {
+        submission_id: r.get(0),
+        sha256: hex::encode(r.get::<_, Vec<u8>>(1)),
+        file_path: r.get(2),
+        file_size: r.get(3),
+        agent_id: r.get(4),
+        incident_id: r.get(5),
+        source: r.get(6),
+        submitted_by: r.get(7),
+        reason: r.get(8),
+        mode: r.get(9),
+        status: r.get(10),
+        queued_at: r.get(11),
+        submitted_at: r.get(12),
+        finished_at: r.get(13),
+        sandbox_task_id: r.get(14),
+        score: r.get(15),
+        verdict: r.get(16),
+        report_sha256: r.get::<_, Option<Vec<u8>>>(17).map(hex::encode),
+        detection_id: r.get(18),
+        failure_reason: r.get(19),
+    }
+}
+
+/// A submission to record (sha256 is lowercase hex).
+#[derive(Debug, Clone)]
+pub struct NewSubmission {
+    pub sha256: String,
+    pub file_path: Option<String>,
+    pub file_size: Option<i64>,
+    pub agent_id: Option<Uuid>,
+    pub incident_id: Option<Uuid>,
+    /// agent or operator
+    pub source: &'static str,
+    pub submitted_by: String,
+    pub reason: Option<String>,
+    pub mode: SubmitMode,
+}
+
+/// Record a submission, or return the open/reported one for the same SHA-256 (second value
+/// false). A queued hash submission is upgraded when a sample arrives for it. None when a
+/// concurrent submission of the same hash kept moving; the caller may retry.
+pub async fn submit(db: &Client, new: &NewSubmission) -> Result<Option<(SandboxSubmission, bool)>, tokio_postgres::Error> {
+    let sha256 = hex::decode(&new.sha256).unwrap_or_default();
+    // Two rounds: a concurrent insert of the same hash wins the unique index, then is returned
+    for _ in 0..2 {
+        let existing = db
+            .query_opt(
+                &format!(
+                    r#"
+                    SELECT {} FROM sandbox_submissions
+                    WHERE sha256 = $1
+                      AND (status IN ('queued', 'submitted', 'reported')
+                           OR ($2 = 'hash' AND status = 'not_found' AND finished_at > now() - make_interval(secs => $3)))
+                    ORDER BY queued_at DESC
+                    LIMIT 1
+                    "#,
+                    SUBMISSION_COLUMNS
+                ),
+                &[&sha256, &new.mode.as_str(), &(NOT_FOUND_RETRY_SECS as f64)],
+            )
+            .await?;
+        if let Some(row) = existing {
+            let mut submission = submission_from_row(&row);
+            if new.mode == SubmitMode::Sample && submission.status == "queued" && submission.mode == "hash" {
+                let upgraded = db
+                    .execute(
+                        "UPDATE sandbox_submissions SET mode = 'sample' WHERE submission_id = $1 AND status = 'queued'",
+                        &[&submission.submission_id],
+                    )
+                    .await?;
+                if upgraded == 1 {
+                    submission.mode = SubmitMode::Sample.as_str().to_string();
+                }
+            }
+            return Ok(Some((submission, false)));
+        }
+        let inserted = db
+            .query_opt(
+                &format!(
+                    r#"
+                    INSERT INTO sandbox_submissions (
+                        submission_id, sha256, file_path, file_size, agent_id, incident_id, source, submitted_by,
+                        reason, mode
+                    )
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                    ON CONFLICT DO NOTHING
+                    RETURNING {}
+                    "#,
+                    SUBMISSION_COLUMNS
+                ),
+                &[
+                    &Uuid::new_v4(),
+                    &sha256,
+                    &new.file_path,
+                    &new.file_size,
+                    &new.agent_id,
+                    &new.incident_id,
+                    &new.source,
+                    &new.submitted_by,
+                    &new.reason,
+                    &new.mode.as_str(),
+                ],
+            )
+            .await?;
+        if let Some(row) = inserted {
+            return Ok(Some((submission_from_row(&row), true)));
+        }
+    }
+    Ok(None)
+}
+
+/// Cuckoo REST API client
+pub struct CuckooClient {
+    client: reqwest::Client,
+    api_url: String,
+    api_token: Option<String>,
+}
+
+impl CuckooClient {
+    pub fn new(config: &SandboxConfig) -> Result<Self, String> {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| format!("Failed to create sandbox HTTP client: {}", e))?;
+        Ok(Self { client, api_url: config.api_url.clone(), api_token: config.api_token.clone() })
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let req = self.client.request(method, format!("{}{}", self.api_url, path));
+        match &self.api_token {
+            Some(token) => req.bearer_auth(token),
+            None => req,
+        }
+    }
+
+    /// JSON body of a successful response, at most `limit` bytes; None on 404.
+    async fn read(&self, res: reqwest::Response, limit: u64) -> Result<Option<Vec<u8>>, String> {
+        if res.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !res.status().is_success() {
+            return Err(format!("{} returned HTTP {}", res.url().path(), res.status()));
+        }
+        let path = res.url().path().to_string();
+        let mut res = res;
+        let mut body = Vec::new();
+        while let Some(chunk) = res.chunk().await.map_err(|e| format!("{}: {}", path, e))? {
+            if (body.len() + chunk.len()) as u64 > limit {
+                return Err(format!("{} response exceeds {} bytes", path, limit));
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(Some(body))
+    }
+
+    async fn get_json(&self, path: &str) -> Result<Option<JsonValue>, String> {
+        let res = self.request(reqwest::Method::GET, path).send().await.map_err(|e| format!("{}: {}", path, e))?;
+        match self.read(res, 1024 * 1024).await? {
+            Some(body) => serde_json::from_slice(&body).map(Some).map_err(|e| format!("{}: invalid JSON: {}", path, e)),
+            None => Ok(None),
+        }
+    }
+
+    /// Detonate a sample; returns the task id.
+    pub async fn submit_file(&self, file_name: &str, sample: Vec<u8>) -> Result<i64, String> {
+        let part = reqwest::multipart::Part::bytes(sample).file_name(file_name.to_string());
+        let res = self
+            .request(reqwest::Method::POST, "/tasks/create/file")
+            .multipart(reqwest::multipart::Form::new().part("file", part))
+            .send()
+            .await
+            .map_err(|e| format!("/tasks/create/file: {}", e))?;
+        let body = self.read(res, 1024 * 1024).await?.ok_or("/tasks/create/file returned HTTP 404")?;
+        let response: JsonValue =
+            serde_json::from_slice(&body).map_err(|e| format!("/tasks/create/file: invalid JSON: {}", e))?;
+        parse_task_id(&response).ok_or_else(|| "/tasks/create/file returned no task id".to_string())
+    }
+
+    /// Latest task the sandbox holds for a SHA-256, if it knows the file.
+    pub async fn lookup(&self, sha256: &str) -> Result<Option<i64>, String> {
+        let Some(file) = self.get_json(&format!("/files/view/sha256/{}", sha256)).await? else {
+            return Ok(None);
+        };
+        let Some(sample_id) = file.pointer("/sample/id").and_then(JsonValue::as_i64) else {
+            return Ok(None);
+        };
+        Ok(self.get_json(&format!("/tasks/sample/{}", sample_id)).await?.as_ref().and_then(latest_task))
+    }
+
+    pub async fn task_state(&self, task_id: i64) -> Result<TaskState, String> {
+        let path = format!("/tasks/view/{}", task_id);
+        let view = self.get_json(&path).await?.ok_or_else(|| format!("task {} not found in the sandbox", task_id))?;
+        task_state(&view).ok_or_else(|| format!("{}: no task status", path))
+    }
+
+    /// Raw JSON report of a reported task.
+    pub async fn report(&self, task_id: i64, max_bytes: u64) -> Result<Vec<u8>, String> {
+        let path = format!("/tasks/report/{}", task_id);
+        let res = self.request(reqwest::Method::GET, &path).send().await.map_err(|e| format!("{}: {}", path, e))?;
+        self.read(res, max_bytes).await?.ok_or_else(|| format!("{}: report not found", path))
+    }
+}
+
+/// Outcome of one worker pass
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct WorkerPass {
+    pub submitted: usize,
+    pub not_found: usize,
+    pub reported: usize,
+    pub malicious: usize,
+    pub failed: usize,
+}
+
+/// Submits queued submissions and collects verdicts. Runs on its own connection: the store
+/// transaction that records a verdict also carries the submission update.
+pub struct SandboxWorker {
+    config: Arc<SandboxConfig>,
+    client: CuckooClient,
+    db: Arc<Client>,
+    store: Arc<dyn TelemetryStore>,
+    dashboard: Option<Arc<DashboardCache>>,
+}
+
+impl SandboxWorker {
+    pub fn new(config: Arc<SandboxConfig>, db: Arc<Client>, store: Arc<dyn TelemetryStore>) -> Result<Self, String> {
+        let client = CuckooClient::new(&config)?;
+        Ok(Self { config, client, db, store, dashboard: None })
+    }
+
+    /// Drop the dashboard's severity counts whenever a malicious verdict commits its detection.
+    pub fn with_dashboard_cache(mut self, dashboard: Arc<DashboardCache>) -> Self {
+        self.dashboard = Some(dashboard);
+        self
+    }
+
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut tick = tokio::time::interval(self.config.poll_interval);
+            loop {
+                tick.tick().await;
+                match self.run_once().await {
+                    Ok(pass) if pass != WorkerPass::default() => info!(
+                        "Sandbox pass | submitted={} | not_found={} | reported={} | malicious={} | failed={}",
+                        pass.submitted, pass.not_found, pass.reported, pass.malicious, pass.failed
+                    ),
+                    Ok(_) => {}
+                    Err(e) => error!("Sandbox pass failed: {}", e),
+                }
+            }
+        })
+    }
+
+    /// Expire overdue submissions, submit queued ones and collect finished verdicts.
+    pub async fn run_once(&self) -> Result<WorkerPass, String> {
+        let mut pass = WorkerPass::default();
+        let expired = self
+            .db
+            .query(
+                r#"
+                UPDATE sandbox_submissions
+                SET status = 'failed', finished_at = now(), failure_reason = 'no verdict within the sandbox timeout'
+                WHERE status IN ('queued', 'submitted') AND queued_at < now() - make_interval(secs => $1)
+                RETURNING sha256
+                "#,
+                &[&(self.config.timeout_secs as f64)],
+            )
+            .await
+            .map_err(|e| format!("expire sandbox submissions: {}", e))?;
+        for row in &expired {
+            remove_sample(&self.config.spool_dir, &hex::encode(row.get::<_, Vec<u8>>(0)));
+        }
+        pass.failed += expired.len();
+
+        let queued = self
+            .db
+            .query(
+                &format!("SELECT {} FROM sandbox_submissions WHERE status = 'queued' ORDER BY queued_at LIMIT $1", SUBMISSION_COLUMNS),
+                &[&BATCH],
+            )
+            .await
+            .map_err(|e| format!("read queued sandbox submissions: {}", e))?;
+        for row in &queued {
+            let submission = submission_from_row(row);
+            match self.send(&submission).await {
+                Ok(Some(task_id)) => {
+                    self.mark_submitted(&submission, task_id).await?;
+                    pass.submitted += 1;
+                }
+                Ok(None) => {
+                    self.finish(submission.submission_id, "not_found", None).await?;
+                    pass.not_found += 1;
+                }
+                // Sandbox unreachable: stays queued until the timeout
+                Err(SendError::Transient(e)) => warn!("Sandbox submission {} not sent: {}", submission.submission_id, e),
+                Err(SendError::Fatal(e)) => {
+                    self.finish(submission.submission_id, "failed", Some(&e)).await?;
+                    remove_sample(&self.config.spool_dir, &submission.sha256);
+                    pass.failed += 1;
+                }
+            }
+        }
+
+        let submitted = self
+            .db
+            .query(
+                &format!(
+                    "SELECT {} FROM sandbox_submissions WHERE status = 'submitted' ORDER BY submitted_at LIMIT $1",
+                    SUBMISSION_COLUMNS
+                ),
+                &[&BATCH],
+            )
+            .await
+            .map_err(|e| format!("read submitted sandbox submissions: {}", e))?;
+        for row in &submitted {
+            let submission = submission_from_row(row);
+            let Some(task_id) = submission.sandbox_task_id else { continue };
+            match self.client.task_state(task_id).await {
+                Ok(TaskState::Running) => {}
+                Ok(TaskState::Failed(status)) => {
+                    self.finish(submission.submission_id, "failed", Some(&format!("sandbox task {} {}", task_id, status)))
+                        .await?;
+                    pass.failed += 1;
+                }
+                Ok(TaskState::Reported) => match self.collect(&submission, task_id).await {
+                    Ok(verdict) => {
+                        pass.reported += 1;
+                        if verdict == Verdict::Malicious {
+                            pass.malicious += 1;
+                        }
+                    }
+                    Err(SendError::Transient(e)) => warn!("Sandbox report {} not collected: {}", submission.submission_id, e),
+                    Err(SendError::Fatal(e)) => {
+                        self.finish(submission.submission_id, "failed", Some(&e)).await?;
+                        pass.failed += 1;
+                    }
+                },
+                Err(e) => warn!("Sandbox task {} of {} not polled: {}", task_id, submission.submission_id, e),
+            }
+        }
+        Ok(pass)
+    }
+
+    /// Task id of a submission the sandbox accepted, None when a hash is unknown to it.
+    async fn send(&self, submission: &SandboxSubmission) -> Result<Option<i64>, SendError> {
+        if submission.mode == SubmitMode::Hash.as_str() {
+            return self.client.lookup(&submission.sha256).await.map_err(SendError::Transient);
+        }
+        let path = sample_path(&self.config.spool_dir, &submission.sha256);
+        let sample = tokio::fs::read(&path)
+            .await
+            .map_err(|e| SendError::Fatal(format!("sample not in the spool ({}): {}", path.display(), e)))?;
+        // The sandbox only sees the file name, never the agent's directory layout
+        let file_name = submission
+            .file_path
+            .as_deref()
+            .and_then(|p| p.rsplit(['/', '\\']).next())
+            .filter(|n| !n.is_empty())
+            .unwrap_or(&submission.sha256)
+            .to_string();
+        self.client.submit_file(&file_name, sample).await.map(Some).map_err(SendError::Transient)
+    }
+
+    async fn mark_submitted(&self, submission: &SandboxSubmission, task_id: i64) -> Result<(), String> {
+        self.db
+            .execute(
+                r#"
+                UPDATE sandbox_submissions SET status = 'submitted', submitted_at = now(), sandbox_task_id = $2
+                WHERE submission_id = $1 AND status = 'queued'
+                "#,
+                &[&submission.submission_id, &task_id],
+            )
+            .await
+            .map_err(|e| format!("record sandbox task: {}", e))?;
+        remove_sample(&self.config.spool_dir, &submission.sha256);
+        info!(
+            "Sandbox submission sent | submission_id={} | sha256={} | mode={} | task_id={}",
+            submission.submission_id, submission.sha256, submission.mode, task_id
+        );
+        Ok(())
+    }
+
+    async fn finish(&self, submission_id: Uuid, status: &str, reason: Option<&str>) -> Result<(), String> {
+        self.db
+            .execute(
+                r#"
+                UPDATE sandbox_submissions SET status = $2, finished_at = now(), failure_reason = $3
+                WHERE submission_id = $1 AND status IN ('queued', 'submitted')
+                "#,
+                &[&submission_id, &status, &reason],
+            )
+            .await
+            .map_err(|e| format!("record sandbox outcome: {}", e))?;
+        match reason {
+            Some(reason) => warn!("Sandbox submission {} {}: {}", submission_id, status, reason),
+            None => info!("Sandbox submission {} {}", submission_id, status),
+        }
+        Ok(())
+    }
+
+    /// Fetch the report, spool it, and record the verdict (with its detection when malicious)
+    /// in one transaction.
+    async fn collect(&self, submission: &SandboxSubmission, task_id: i64) -> Result<Verdict, SendError> {
+        let report = self.client.report(task_id, self.config.max_report_bytes).await.map_err(SendError::Transient)?;
+        let parsed: JsonValue =
+            serde_json::from_slice(&report).map_err(|e| SendError::Fatal(format!("report is not JSON: {}", e)))?;
+        let score = parse_score(&parsed).ok_or_else(|| SendError::Fatal("report carries no score".to_string()))?;
+        let verdict = self.config.verdict(score);
+        let report_sha256 = hex::encode(Sha256::digest(&report));
+
+        let ingestion_component_id = self
+            .store
+            .ingestion_component()
+            .await
+            .map_err(|e| SendError::Transient(format!("ingestion component: {}", e)))?;
+        let mut tx = self.store.begin().await.map_err(|e| SendError::Transient(format!("begin: {}", e)))?;
+        let detection_id = if verdict == Verdict::Malicious {
+            match tx.insert_detection(&sandbox_detection(&self.config, submission, task_id, score)).await {
+                Ok(id) => Some(id),
+                Err(e) => {
+                    tx.rollback().await;
+                    return Err(SendError::Transient(format!("detection insert: {}", e)));
+                }
+            }
+        } else {
+            None
+        };
+        let reported_at = Utc::now();
+        let updated = self
+            .db
+            .execute(
+                r#"
+                UPDATE sandbox_submissions
+                SET status = 'reported', finished_at = $2, score = $3, verdict = $4, report_sha256 = $5, detection_id = $6
+                WHERE submission_id = $1 AND status = 'submitted'
+                "#,
+                &[
+                    &submission.submission_id,
+                    &reported_at,
+                    &score,
+                    &verdict.as_str(),
+                    &hex::decode(&report_sha256).unwrap_or_default(),
+                    &detection_id,
+                ],
+            )
+            .await;
+        match updated {
+            Ok(1) => {}
+            Ok(_) => {
+                // Expired meanwhile: no verdict without its row
+                tx.rollback().await;
+                return Err(SendError::Transient("submission is no longer open".to_string()));
+            }
+            Err(e) => {
+                tx.rollback().await;
+                return Err(SendError::Transient(format!("verdict update: {}", e)));
+            }
+        }
+
+        let manifest = SpoolManifest {
+            submission_id: submission.submission_id,
+            sha256: submission.sha256.clone(),
+            file_path: submission.file_path.clone(),
+            file_size: submission.file_size,
+            agent_id: submission.agent_id,
+            incident_id: submission.incident_id,
+            source: submission.source.clone(),
+            submitted_by: submission.submitted_by.clone(),
+            mode: submission.mode.clone(),
+            sandbox_url: self.config.api_url.clone(),
+            sandbox_task_id: task_id,
+            queued_at: submission.queued_at,
+            reported_at,
+            score,
+            verdict,
+            detection_id,
+            report_file: format!("{}.report", submission.submission_id),
+            report_sha256: report_sha256.clone(),
+            report_bytes: report.len() as u64,
+        };
+        // Spool before commit: a recorded verdict always has its report on disk
+        let spool_dir = self.config.spool_dir.clone();
+        let spooled = {
+            let (spool_dir, manifest) = (spool_dir.clone(), manifest.clone());
+            tokio::task::spawn_blocking(move || write_spool(&spool_dir, &manifest, &report)).await
+        };
+        if !matches!(spooled, Ok(Ok(()))) {
+            tx.rollback().await;
+            remove_spool(&spool_dir, submission.submission_id);
+            return Err(SendError::Transient(format!("report not spooled in {}", spool_dir.display())));
+        }
+
+        let payload = serde_json::json!({
+            "submission_id": submission.submission_id.to_string(),
+            "sha256": submission.sha256,
+            "agent_id": submission.agent_id.map(|id| id.to_string()),
+            "sandbox_task_id": task_id,
+            "score": score,
+            "verdict": verdict.as_str(),
+            "report_sha256": report_sha256,
+            "detection_id": detection_id.map(|id| id.to_string()),
+        });
+        let audit = AuditRecord {
+            actor_component_id: Some(ingestion_component_id),
+            actor_agent_id: submission.agent_id,
+            action: "SANDBOX_VERDICT".to_string(),
+            object_type: "other".to_string(),
+            object_id: Some(submission.submission_id),
+            event_time: Some(reported_at),
+            payload_json: payload.clone(),
+            payload_sha256: Sha256::digest(payload.to_string().as_bytes()).to_vec(),
+        };
+        if let Err(e) = tx.append_audit(&audit).await {
+            tx.rollback().await;
+            remove_spool(&spool_dir, submission.submission_id);
+            return Err(SendError::Transient(format!("audit: {}", e)));
+        }
+        if let Err(e) = tx.commit().await {
+            remove_spool(&spool_dir, submission.submission_id);
+            return Err(SendError::Transient(format!("commit: {}", e)));
+        }
+        if let (Some(dashboard), Some(_)) = (&self.dashboard, detection_id) {
+            dashboard.invalidate_detections();
+        }
+        info!(
+            "Sandbox verdict | submission_id={} | sha256={} | task_id={} | score={} | verdict={}",
+            submission.submission_id, submission.sha256, task_id, score, verdict.as_str()
+        );
+        Ok(verdict)
+    }
+}
+
+/// Why a submission did not move on: transient errors are retried next pass, fatal ones fail it.
+#[derive(Debug)]
+enum SendError {
+    Transient(String),
+    Fatal(String),
+}
+
+/// The sandbox_malicious_verdict detection of a reported submission.
+pub fn sandbox_detection(config: &SandboxConfig, submission: &SandboxSubmission, task_id: i64, score: f64) -> DetectionRecord {
+    let artifacts = serde_json::json!({
+        "submission_id": submission.submission_id.to_string(),
+        "sha256": submission.sha256,
+        "file_path": submission.file_path,
+        "agent_id": submission.agent_id.map(|id| id.to_string()),
+        "incident_id": submission.incident_id.map(|id| id.to_string()),
+        "sandbox_task_id": task_id,
+        "score": score,
+        "malicious_score": config.malicious_score,
+    });
+    DetectionRecord {
+        detection_engine: DETECTION_ENGINE.to_string(),
+        detection_name: DETECTION_NAME.to_string(),
+        detection_category: Some("malware".to_string()),
+        severity: "critical".to_string(),
+        confidence: (score / 10.0).clamp(0.0, 1.0),
+        reasoning: format!(
+            "Sandbox task {} scored {}/10 for {} (malicious from {})",
+            task_id,
+            score,
+            submission.file_path.as_deref().unwrap_or(&submission.sha256),
+            config.malicious_score
+        ),
+        artifacts,
+        // Same key for every verdict on the same file
+        deterministic_key: Sha256::digest(format!("sandbox|{}", submission.sha256).as_bytes()).to_vec(),
+    }
+}
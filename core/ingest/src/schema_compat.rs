@@ -0,0 +1,471 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/schema_compat.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Rolling-upgrade schema compatibility - schema generation matrix enforced at startup (at most one version of skew), tolerance of columns added by a newer schema, and dual-write shims for renamed columns
+
+/*
+ * Schema Compatibility
+ *
+ * During an upgrade the schema moves first (update bundle migrations) and services follow one by
+ * one, so a service may briefly run against a schema one generation newer than the one it was
+ * built for. ransomeye.schema_versions records the generations; the highest row is current.
+ *
+ * Matrix (code = SCHEMA_VERSION of the running build, db = current generation):
+ *   db == code                                   -> current
+ *   db == code + 1 and min_code_version <= code  -> compatible (old code, newer schema)
+ *   db <  code                                   -> refused: migrate the schema first
+ *   db >  code + 1                               -> refused: skew exceeds one version
+ *   min_code_version > code                      -> refused: breaking generation
+ *
+ * Unknown columns (live columns the build's authoritative schema does not declare) are tolerated
+ * when nullable or defaulted; a NOT NULL column without a default on a table the service writes
+ * would fail every insert, so the service refuses to start instead.
+ *
+ * Renamed columns: a migration calls compat_rename_column(table, old, new, since_version), which
+ * adds the new column, backfills it and installs compat_dual_write(). Both names stay in sync
+ * until compat_retire_rename(table, old) drops the old column one release later. The functions
+ * are installed by the orchestrator (install_shims) and run inside the migration transaction.
+ */
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+use tokio_postgres::Client;
+use tracing::{info, warn};
+
+/// Schema generation this build reads and writes. Bump together with the migration that adds the
+/// matching schema_versions row.
+pub const SCHEMA_VERSION: i32 = 1;
+
+/// Generation of a database created before schema_versions existed
+pub const BASELINE_SCHEMA_VERSION: i32 = 1;
+
+/// How many generations the schema may be ahead of the running code
+pub const MAX_SCHEMA_SKEW: i32 = 1;
+
+/// Authoritative schema this build was written against (declared tables and columns)
+pub const AUTHORITATIVE_SCHEMA: &str = include_str!("../../../ransomeye_db_core/schema/schema.sql");
+
+/// Tables ingest inserts into; unknown required columns on these would break its writes.
+pub const INGEST_WRITTEN_TABLES: &[&str] = &[
+    "agent_api_tokens",
+    "agent_drop_counters",
+    "agent_key_pins",
+    "agent_log_segments",
+    "agents",
+    "annotation_revisions",
+    "annotations",
+    "components",
+    "detection_results",
+    "detection_suppressions",
+    "dpi_probe_telemetry",
+    "error_events",
+    "feature_flags",
+    "host_inventory",
+    "identity_conflicts",
+    "immutable_audit_log",
+    "incident_closures",
+    "ingest_outbox",
+    "ingest_rate_windows",
+    "ingest_replay_nonces",
+    "ingest_replay_state",
+    "legal_holds",
+    "linux_agent_telemetry",
+    "maintenance_windows",
+    "memory_acquisition_chunks",
+    "memory_acquisitions",
+    "pcap_captures",
+    "quarantined_events",
+    "raw_events",
+    "rule_metrics",
+    "sandbox_submissions",
+    "signature_validation_events",
+    "telemetry_drops_daily",
+    "webhook_deliveries",
+    "webhook_subscriptions",
+    "yara_rule_packs",
+    "yara_scan_requests",
+];
+
+/// Dual-write trigger and the rename/retire helpers migrations call (idempotent).
+pub const SHIM_SQL: &str = r#"
+CREATE OR REPLACE FUNCTION ransomeye.compat_dual_write()
+RETURNS trigger
+LANGUAGE plpgsql
+AS $$
+DECLARE
+  r record;
+  new_row jsonb := to_jsonb(NEW);
+  old_row jsonb;
+  patch jsonb := '{}'::jsonb;
+BEGIN
+  IF TG_OP = 'UPDATE' THEN
+    old_row := to_jsonb(OLD);
+  END IF;
+  FOR r IN
+    SELECT old_column, new_column
+    FROM ransomeye.schema_column_renames
+    WHERE table_name = TG_TABLE_NAME AND retired_at IS NULL
+  LOOP
+    IF TG_OP = 'UPDATE' THEN
+      -- The name the statement changed wins
+      IF (new_row -> r.old_column) IS DISTINCT FROM (old_row -> r.old_column)
+         AND (new_row -> r.new_column) IS NOT DISTINCT FROM (old_row -> r.new_column) THEN
+        patch := patch || jsonb_build_object(r.new_column, new_row -> r.old_column);
+      ELSIF (new_row -> r.new_column) IS DISTINCT FROM (old_row -> r.new_column) THEN
+        patch := patch || jsonb_build_object(r.old_column, new_row -> r.new_column);
+      END IF;
+    ELSIF jsonb_typeof(new_row -> r.new_column) = 'null' THEN
+      patch := patch || jsonb_build_object(r.new_column, new_row -> r.old_column);
+    ELSIF jsonb_typeof(new_row -> r.old_column) = 'null' THEN
+      patch := patch || jsonb_build_object(r.old_column, new_row -> r.new_column);
+    END IF;
+  END LOOP;
+  IF patch <> '{}'::jsonb THEN
+    NEW := jsonb_populate_record(NEW, patch);
+  END IF;
+  RETURN NEW;
+END;
+$$;
+
+CREATE OR REPLACE FUNCTION ransomeye.compat_rename_column(p_table text, p_old text, p_new text, p_since integer)
+RETURNS void
+LANGUAGE plpgsql
+AS $$
+DECLARE
+  col record;
+BEGIN
+  SELECT format_type(a.atttypid, a.atttypmod) AS coltype, a.atthasdef AS hasdef
+  INTO col
+  FROM pg_attribute a
+  WHERE a.attrelid = format('ransomeye.%I', p_table)::regclass
+    AND a.attname = p_old
+    AND a.attnum > 0
+    AND NOT a.attisdropped;
+  IF NOT FOUND THEN
+    RAISE EXCEPTION 'compat_rename_column: column %.% does not exist', p_table, p_old;
+  END IF;
+  -- With a default on either side the trigger cannot tell which name an INSERT wrote
+  IF col.hasdef THEN
+    RAISE EXCEPTION 'compat_rename_column: %.% has a default; rename it with a dedicated migration', p_table, p_old;
+  END IF;
+  EXECUTE format('ALTER TABLE ransomeye.%I ADD COLUMN %I %s NULL', p_table, p_new, col.coltype);
+  EXECUTE format('UPDATE ransomeye.%I SET %I = %I', p_table, p_new, p_old);
+  INSERT INTO ransomeye.schema_column_renames (table_name, old_column, new_column, since_version)
+  VALUES (p_table, p_old, p_new, p_since);
+  EXECUTE format('DROP TRIGGER IF EXISTS trg_compat_dual_write ON ransomeye.%I', p_table);
+  EXECUTE format(
+    'CREATE TRIGGER trg_compat_dual_write BEFORE INSERT OR UPDATE ON ransomeye.%I '
+    'FOR EACH ROW EXECUTE FUNCTION ransomeye.compat_dual_write()',
+    p_table
+  );
+END;
+$$;
+
+CREATE OR REPLACE FUNCTION ransomeye.compat_retire_rename(p_table text, p_old text)
+RETURNS void
+LANGUAGE plpgsql
+AS $$
+DECLARE
+  r record;
+  old_not_null boolean;
+BEGIN
+  SELECT * INTO r
+  FROM ransomeye.schema_column_renames
+  WHERE table_name = p_table AND old_column = p_old AND retired_at IS NULL;
+  IF NOT FOUND THEN
+    RAISE EXCEPTION 'compat_retire_rename: no active rename of %.%', p_table, p_old;
+  END IF;
+  SELECT a.attnotnull INTO old_not_null
+  FROM pg_attribute a
+  WHERE a.attrelid = format('ransomeye.%I', p_table)::regclass AND a.attname = p_old AND NOT a.attisdropped;
+  EXECUTE format('ALTER TABLE ransomeye.%I DROP COLUMN %I', p_table, p_old);
+  IF old_not_null THEN
+    EXECUTE format('ALTER TABLE ransomeye.%I ALTER COLUMN %I SET NOT NULL', p_table, r.new_column);
+  END IF;
+  UPDATE ransomeye.schema_column_renames SET retired_at = now()
+  WHERE table_name = p_table AND old_column = p_old;
+  IF NOT EXISTS (
+    SELECT 1 FROM ransomeye.schema_column_renames WHERE table_name = p_table AND retired_at IS NULL
+  ) THEN
+    EXECUTE format('DROP TRIGGER IF EXISTS trg_compat_dual_write ON ransomeye.%I', p_table);
+  END IF;
+END;
+$$;
+"#;
+
+/// Current row of ransomeye.schema_versions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SchemaGeneration {
+    pub version: i32,
+    pub min_code_version: i32,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "mode")]
+pub enum Compatibility {
+    /// The schema is the generation this build was written for
+    Current,
+    /// The schema is one generation ahead (rolling upgrade in progress)
+    SchemaAhead { schema_version: i32 },
+}
+
+/// Active (not retired) dual-written rename.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ColumnRename {
+    pub table: String,
+    pub old_column: String,
+    pub new_column: String,
+    pub since_version: i32,
+}
+
+/// Live column as reported by pg_attribute.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiveColumn {
+    pub table: String,
+    pub column: String,
+    pub not_null: bool,
+    pub has_default: bool,
+}
+
+/// Live columns the build does not declare, split by whether its inserts can ignore them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct UnknownColumns {
+    /// Nullable or defaulted: inserts that leave them out still succeed
+    pub tolerated: Vec<String>,
+    /// NOT NULL without a default: inserts that leave them out fail
+    pub blocking: Vec<String>,
+}
+
+/// Outcome of the startup check.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CompatReport {
+    pub code_schema_version: i32,
+    pub generation: SchemaGeneration,
+    pub compatibility: Compatibility,
+    pub renames: Vec<ColumnRename>,
+    pub unknown_columns: UnknownColumns,
+}
+
+/// Apply the compatibility matrix (see the module comment).
+pub fn check_compatibility(code_version: i32, generation: &SchemaGeneration) -> Result<Compatibility, String> {
+    let db = generation.version;
+    if db < code_version {
+        return Err(format!(
+            "schema generation {db} is older than this build (generation {code_version}); apply the schema migrations before upgrading services"
+        ));
+    }
+    if db - code_version > MAX_SCHEMA_SKEW {
+        return Err(format!(
+            "schema generation {db} is {} ahead of this build (generation {code_version}); at most {MAX_SCHEMA_SKEW} version of skew is supported",
+            db - code_version
+        ));
+    }
+    if generation.min_code_version > code_version {
+        return Err(format!(
+            "schema generation {db} requires code built for generation {} or newer (this build: {code_version})",
+            generation.min_code_version
+        ));
+    }
+    Ok(if db == code_version { Compatibility::Current } else { Compatibility::SchemaAhead { schema_version: db } })
+}
+
+/// Renames at least one release old: every compatible build already uses the new name, so the
+/// old column is ready to be retired.
+pub fn retirable_renames(renames: &[ColumnRename], schema_version: i32) -> Vec<&ColumnRename> {
+    renames.iter().filter(|r| schema_version - r.since_version >= MAX_SCHEMA_SKEW).collect()
+}
+
+/// table -> declared column names, from the CREATE TABLE IF NOT EXISTS blocks of a schema file.
+pub fn declared_columns(schema_sql: &str) -> BTreeMap<String, BTreeSet<String>> {
+    let mut tables = BTreeMap::new();
+    let mut lines = schema_sql.lines();
+    while let Some(line) = lines.next() {
+        let Some(rest) = line.trim().strip_prefix("CREATE TABLE IF NOT EXISTS ") else {
+            continue;
+        };
+        let table = rest.split_whitespace().next().unwrap_or("");
+        let table = table.strip_prefix("ransomeye.").unwrap_or(table).to_string();
+        let mut columns = BTreeSet::new();
+        for line in lines.by_ref() {
+            let trimmed = line.trim();
+            if trimmed.starts_with(");") {
+                break;
+            }
+            let Some(name) = trimmed.split_whitespace().next() else {
+                continue;
+            };
+            let name = name.trim_matches('"');
+            let is_ident = name.starts_with(|c: char| c.is_ascii_lowercase() || c == '_')
+                && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+            // CONSTRAINT/PRIMARY KEY/CHECK lines and expression continuations are not columns
+            if is_ident {
+                columns.insert(name.to_string());
+            }
+        }
+        tables.insert(table, columns);
+    }
+    tables
+}
+
+/// Live columns of `tables` the declared schema does not know about.
+pub fn unknown_columns(
+    declared: &BTreeMap<String, BTreeSet<String>>,
+    live: &[LiveColumn],
+    tables: &[&str],
+) -> UnknownColumns {
+    let mut out = UnknownColumns::default();
+    for col in live {
+        if !tables.contains(&col.table.as_str()) {
+            continue;
+        }
+        let Some(known) = declared.get(&col.table) else {
+            continue;
+        };
+        if known.contains(&col.column) {
+            continue;
+        }
+        let name = format!("{}.{}", col.table, col.column);
+        if col.not_null && !col.has_default {
+            out.blocking.push(name);
+        } else {
+            out.tolerated.push(name);
+        }
+    }
+    out.tolerated.sort();
+    out.blocking.sort();
+    out
+}
+
+/// Current generation; None when schema_versions is empty (database predates it).
+pub async fn load_generation(client: &Client) -> Result<Option<SchemaGeneration>, String> {
+    let row = client
+        .query_opt(
+            r#"
+            SELECT schema_version, min_code_version, description
+            FROM ransomeye.schema_versions
+            ORDER BY schema_version DESC
+            LIMIT 1
+            "#,
+            &[],
+        )
+        .await
+        .map_err(|e| format!("Failed to read schema_versions: {e}"))?;
+    Ok(row.map(|r| SchemaGeneration { version: r.get(0), min_code_version: r.get(1), description: r.get(2) }))
+}
+
+pub async fn load_renames(client: &Client) -> Result<Vec<ColumnRename>, String> {
+    let rows = client
+        .query(
+            r#"
+            SELECT table_name, old_column, new_column, since_version
+            FROM ransomeye.schema_column_renames
+            WHERE retired_at IS NULL
+            ORDER BY table_name, old_column
+            "#,
+            &[],
+        )
+        .await
+        .map_err(|e| format!("Failed to read schema_column_renames: {e}"))?;
+    Ok(rows
+        .iter()
+        .map(|r| ColumnRename { table: r.get(0), old_column: r.get(1), new_column: r.get(2), since_version: r.get(3) })
+        .collect())
+}
+
+async fn load_live_columns(client: &Client, tables: &[&str]) -> Result<Vec<LiveColumn>, String> {
+    let tables: Vec<String> = tables.iter().map(|t| t.to_string()).collect();
+    let rows = client
+        .query(
+            r#"
+            SELECT c.relname::text, a.attname::text, a.attnotnull, a.atthasdef
+            FROM pg_attribute a
+            JOIN pg_class c ON c.oid = a.attrelid
+            JOIN pg_namespace n ON n.oid = c.relnamespace
+            WHERE n.nspname = 'ransomeye'
+              AND c.relkind IN ('r', 'p')
+              AND c.relname = ANY($1)
+              AND a.attnum > 0
+              AND NOT a.attisdropped
+            "#,
+            &[&tables],
+        )
+        .await
+        .map_err(|e| format!("Failed to read live columns: {e}"))?;
+    Ok(rows
+        .iter()
+        .map(|r| LiveColumn { table: r.get(0), column: r.get(1), not_null: r.get(2), has_default: r.get(3) })
+        .collect())
+}
+
+/// Record the baseline generation on databases that predate schema_versions (orchestrator only).
+pub async fn ensure_baseline(client: &Client) -> Result<(), String> {
+    client
+        .execute(
+            r#"
+            INSERT INTO ransomeye.schema_versions (schema_version, min_code_version, description)
+            SELECT $1, $1, 'baseline'
+            WHERE NOT EXISTS (SELECT 1 FROM ransomeye.schema_versions)
+            "#,
+            &[&BASELINE_SCHEMA_VERSION],
+        )
+        .await
+        .map_err(|e| format!("Failed to record baseline schema generation: {e}"))?;
+    Ok(())
+}
+
+/// Install the dual-write trigger function and rename helpers (orchestrator only).
+pub async fn install_shims(client: &Client) -> Result<(), String> {
+    client
+        .batch_execute(SHIM_SQL)
+        .await
+        .map_err(|e| format!("Failed to install schema compatibility shims: {e}"))
+}
+
+/// FAIL-CLOSED startup check for `component`: compatibility matrix, then unknown columns on the
+/// tables it writes. Err means the service must not start.
+pub async fn enforce(client: &Client, component: &str, written_tables: &[&str]) -> Result<CompatReport, String> {
+    let generation = load_generation(client)
+        .await?
+        .ok_or_else(|| "schema_versions is empty; start the orchestrator to record the schema generation".to_string())?;
+    let compatibility = check_compatibility(SCHEMA_VERSION, &generation)?;
+    let renames = load_renames(client).await?;
+    let live = load_live_columns(client, written_tables).await?;
+    let unknown = unknown_columns(&declared_columns(AUTHORITATIVE_SCHEMA), &live, written_tables);
+    if !unknown.blocking.is_empty() {
+        return Err(format!(
+            "schema generation {} has required columns this build does not write: {}",
+            generation.version,
+            unknown.blocking.join(", ")
+        ));
+    }
+
+    match compatibility {
+        Compatibility::Current => info!(
+            "Schema compatibility | component={} | generation={} | mode=current | renamed_columns={}",
+            component, generation.version, renames.len()
+        ),
+        Compatibility::SchemaAhead { schema_version } => warn!(
+            "Schema compatibility | component={} | build generation={} | schema generation={} | mode=compat | unknown columns tolerated: [{}] | dual-written renames: [{}]",
+            component,
+            SCHEMA_VERSION,
+            schema_version,
+            unknown.tolerated.join(", "),
+            renames.iter().map(|r| format!("{}.{}->{}", r.table, r.old_column, r.new_column)).collect::<Vec<_>>().join(", ")
+        ),
+    }
+    for r in retirable_renames(&renames, generation.version) {
+        warn!(
+            "Renamed column {}.{} -> {} (since generation {}) is past its release cycle; retire it with compat_retire_rename",
+            r.table, r.old_column, r.new_column, r.since_version
+        );
+    }
+
+    Ok(CompatReport {
+        code_schema_version: SCHEMA_VERSION,
+        generation,
+        compatibility,
+        renames,
+        unknown_columns: unknown,
+    })
+}
@@ -12,8 +12,8 @@
 
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
-use ring::signature::{UnparsedPublicKey, RSA_PSS_2048_8192_SHA256};
-use sha2::{Sha256, Digest};
+use crypto::signature::verify_rsa_pss_sha256;
+use crypto::digest::Sha256;
 use x509_parser::prelude::*;
 use x509_parser::public_key::PublicKey;
 use chrono::{DateTime, Utc};
@@ -159,7 +159,7 @@ impl IdentityVerifier {
     fn verify_signature(&self, cert: &X509Certificate, envelope: &EventEnvelope) -> Result<(), IdentityError> {
         // Extract public key from certificate
         // x509-parser provides the public key in SubjectPublicKeyInfo format
-        // For ring, we need the raw public key bytes
+        // The crypto backend takes the raw public key bytes
         let public_key_info = cert.public_key();
         
         // Verify it's RSA
@@ -171,7 +171,7 @@ impl IdentityVerifier {
         match public_key {
             PublicKey::RSA(_) => {
                 // RSA key - use the raw public key bytes
-                // ring expects the public key in SubjectPublicKeyInfo format for UnparsedPublicKey
+                // Passed through as the SubjectPublicKeyInfo bytes the backend expects
                 let public_key_bytes = public_key_info.raw;
                 
                 // Serialize envelope for signing (same as signature.rs)
@@ -188,10 +188,8 @@ impl IdentityVerifier {
                         format!("Failed to decode signature: {}", e)
                     ))?;
                 
-                // Verify signature using ring
-                let unparsed_key = UnparsedPublicKey::new(&RSA_PSS_2048_8192_SHA256, public_key_bytes);
-                
-                unparsed_key.verify(&hash, &signature_bytes)
+                // Verify signature through the selected crypto backend
+                verify_rsa_pss_sha256(public_key_bytes, &hash, &signature_bytes)
                     .map_err(|e| IdentityError::SignatureVerificationFailed(
                         format!("Signature verification failed: {}", e)
                     ))?;
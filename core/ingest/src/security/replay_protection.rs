@@ -10,46 +10,44 @@
  * - Timestamp skew limits
  * - Sequence number monotonicity per producer
  * 
+ * Nonce and sequence state live in a shared_state backend, so several ingest
+ * instances behind a load balancer see each other's events.
+ * 
  * Replay → HARD REJECT + AUDIT LOG
  */
 
 use std::sync::Arc;
-use dashmap::DashMap;
 use chrono::{DateTime, Utc, Duration};
 use tracing::{warn, debug, error};
-use parking_lot::RwLock;
 
 use crate::security::errors::IdentityError;
+use crate::shared_state::{LocalReplayState, ReplayStateBackend, ReplayVerdict};
 
-#[derive(Clone)]
-struct NonceEntry {
-    timestamp: DateTime<Utc>,
-    sequence_number: u64,
-}
-
-#[derive(Clone)]
-struct ProducerState {
-    last_sequence_number: u64,
-    last_timestamp: DateTime<Utc>,
-}
+/// Replay scope of the authentication path; other checks of the same events use their own scope
+pub const AUTH_REPLAY_SCOPE: &str = "auth";
 
 pub struct ReplayProtector {
-    nonce_cache: Arc<DashMap<String, DashMap<String, NonceEntry>>>,
-    producer_states: Arc<DashMap<String, RwLock<ProducerState>>>,
-    nonce_ttl: Duration,
+    /// Nonce and per-producer sequence state: process memory, or Postgres shared by all instances
+    state: Arc<dyn ReplayStateBackend>,
+    scope: &'static str,
     timestamp_tolerance: Duration,
     max_sequence_gap: u64,
 }
 
 impl ReplayProtector {
+    /// In-memory state (single instance).
     pub fn new() -> Result<Self, IdentityError> {
-        Ok(Self {
-            nonce_cache: Arc::new(DashMap::new()),
-            producer_states: Arc::new(DashMap::new()),
-            nonce_ttl: Duration::hours(24),
+        Ok(Self::with_state(Arc::new(LocalReplayState::new()), AUTH_REPLAY_SCOPE))
+    }
+
+    /// State in `state` under `scope` (see shared_state).
+    pub fn with_state(state: Arc<dyn ReplayStateBackend>, scope: &'static str) -> Self {
+        Self {
+            state,
+            scope,
             timestamp_tolerance: Duration::minutes(5),
             max_sequence_gap: 1000, // Allow up to 1000 sequence number gap
-        })
+        }
     }
     
     /// Check for replay attacks
@@ -61,6 +59,18 @@ impl ReplayProtector {
         timestamp: &DateTime<Utc>,
         sequence_number: u64,
     ) -> Result<(), IdentityError> {
+        self.check_replay_sequence(producer_id, nonce, timestamp, sequence_number).await.map(|_| ())
+    }
+
+    /// `check_replay`, returning the producer's previously accepted sequence number (None for a
+    /// producer seen for the first time)
+    pub async fn check_replay_sequence(
+        &self,
+        producer_id: &str,
+        nonce: &str,
+        timestamp: &DateTime<Utc>,
+        sequence_number: u64,
+    ) -> Result<Option<u64>, IdentityError> {
         let now = Utc::now();
         
         // Step 1: Check timestamp tolerance
@@ -73,98 +83,56 @@ impl ReplayProtector {
             ));
         }
         
-        // Step 2: Check nonce uniqueness
-        let producer_nonces = self.nonce_cache
-            .entry(producer_id.to_string())
-            .or_insert_with(DashMap::new);
-        
-        if producer_nonces.contains_key(nonce) {
-            error!("REPLAY ATTACK: Duplicate nonce detected for producer: {}, nonce: {}", 
-                producer_id, nonce);
-            return Err(IdentityError::ReplayAttack(
-                format!("Duplicate nonce: {}", nonce)
-            ));
-        }
-        
-        // Step 3: Check sequence number monotonicity
-        let producer_state = self.producer_states
-            .entry(producer_id.to_string())
-            .or_insert_with(|| {
-                RwLock::new(ProducerState {
-                    last_sequence_number: 0,
-                    last_timestamp: *timestamp,
-                })
-            });
-        
-        {
-            let mut state = producer_state.write();
-            
-            // Check if sequence number is less than last seen (replay)
-            if sequence_number < state.last_sequence_number {
-                error!("REPLAY ATTACK: Sequence number regression for producer: {} (last: {}, current: {})", 
-                    producer_id, state.last_sequence_number, sequence_number);
-                return Err(IdentityError::SequenceNumberViolation(
-                    format!("Sequence number regression: {} < {}", sequence_number, state.last_sequence_number)
-                ));
+        // Step 2: Nonce uniqueness, sequence and timestamp monotonicity, recorded atomically
+        let verdict = self.state
+            .record(self.scope, producer_id, nonce, sequence_number, *timestamp)
+            .await
+            .map_err(|e| {
+                // FAIL-CLOSED: an event that cannot be checked is not accepted
+                error!("Replay state unavailable for producer: {}: {}", producer_id, e);
+                IdentityError::ReplayAttack(format!("Replay state unavailable: {}", e))
+            })?;
+
+        match verdict {
+            ReplayVerdict::DuplicateNonce => {
+                error!("REPLAY ATTACK: Duplicate nonce detected for producer: {}, nonce: {}", 
+                    producer_id, nonce);
+                Err(IdentityError::ReplayAttack(
+                    format!("Duplicate nonce: {}", nonce)
+                ))
             }
-            
-            // Check if sequence number gap is too large (potential attack or data loss)
-            let sequence_gap = sequence_number - state.last_sequence_number;
-            if sequence_gap > self.max_sequence_gap && state.last_sequence_number > 0 {
-                warn!("Large sequence number gap for producer: {} (gap: {})", 
-                    producer_id, sequence_gap);
-                // Allow but log warning - this could indicate legitimate data loss
+            ReplayVerdict::SequenceRegression { last_sequence } => {
+                error!("REPLAY ATTACK: Sequence number regression for producer: {} (last: {}, current: {})", 
+                    producer_id, last_sequence, sequence_number);
+                Err(IdentityError::SequenceNumberViolation(
+                    format!("Sequence number regression: {} < {}", sequence_number, last_sequence)
+                ))
             }
-            
-            // Check if timestamp is before last seen (replay)
-            if *timestamp < state.last_timestamp {
+            ReplayVerdict::TimestampRegression { last_timestamp } => {
                 error!("REPLAY ATTACK: Timestamp regression for producer: {} (last: {}, current: {})", 
-                    producer_id, state.last_timestamp, timestamp);
-                return Err(IdentityError::TimestampOutOfTolerance(
-                    format!("Timestamp regression: {} < {}", timestamp, state.last_timestamp)
-                ));
+                    producer_id, last_timestamp, timestamp);
+                Err(IdentityError::TimestampOutOfTolerance(
+                    format!("Timestamp regression: {} < {}", timestamp, last_timestamp)
+                ))
             }
-            
-            // Step 4: Update state
-            state.last_sequence_number = sequence_number;
-            state.last_timestamp = *timestamp;
-        } // Lock explicitly dropped here
-        
-        // Step 5: Add nonce to cache
-        producer_nonces.insert(nonce.to_string(), NonceEntry {
-            timestamp: *timestamp,
-            sequence_number,
-        });
-        
-        // Step 6: Cleanup expired nonces (async, after releasing all locks)
-        self.cleanup_expired_nonces(producer_id, now).await;
-        
-        debug!("Replay check passed for producer: {} (nonce: {}, seq: {})", 
-            producer_id, nonce, sequence_number);
-        Ok(())
-    }
-    
-    async fn cleanup_expired_nonces(&self, producer_id: &str, now: DateTime<Utc>) {
-        if let Some(producer_nonces) = self.nonce_cache.get(producer_id) {
-            let expired: Vec<String> = producer_nonces
-                .iter()
-                .filter(|entry| {
-                    let age = now - entry.value().timestamp;
-                    age > self.nonce_ttl
-                })
-                .map(|entry| entry.key().clone())
-                .collect();
-            
-            for nonce in expired {
-                producer_nonces.remove(&nonce);
+            ReplayVerdict::Accepted { previous_sequence } => {
+                // Check if sequence number gap is too large (potential attack or data loss)
+                let previous = previous_sequence.unwrap_or(0);
+                let sequence_gap = sequence_number - previous;
+                if sequence_gap > self.max_sequence_gap && previous > 0 {
+                    warn!("Large sequence number gap for producer: {} (gap: {})", 
+                        producer_id, sequence_gap);
+                    // Allow but log warning - this could indicate legitimate data loss
+                }
+                debug!("Replay check passed for producer: {} (nonce: {}, seq: {})", 
+                    producer_id, nonce, sequence_number);
+                Ok(previous_sequence)
             }
         }
     }
     
     /// Get last sequence number for a producer (for debugging)
-    pub fn get_last_sequence_number(&self, producer_id: &str) -> Option<u64> {
-        self.producer_states
-            .get(producer_id)
-            .map(|state| state.read().last_sequence_number)
+    pub async fn get_last_sequence_number(&self, producer_id: &str) -> Option<u64> {
+        self.state.last_sequence_number(self.scope, producer_id).await.ok().flatten()
     }
 }
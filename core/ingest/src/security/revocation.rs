@@ -20,8 +20,8 @@ use x509_parser::revocation_list::*;
 use chrono::{DateTime, Utc};
 use tracing::{warn, debug, error, info};
 use parking_lot::RwLock;
-use sha2::{Sha256, Digest};
-use ring::signature::{UnparsedPublicKey, RSA_PSS_2048_8192_SHA256};
+use crypto::digest::Sha256;
+use crypto::signature::verify_rsa_pss_sha256;
 
 use crate::security::errors::IdentityError;
 use crate::security::trust_store::TrustStore;
@@ -230,9 +230,7 @@ impl RevocationChecker {
         let hash = hasher.finalize();
         
         // Verify signature
-        let unparsed_key = UnparsedPublicKey::new(&RSA_PSS_2048_8192_SHA256, root_public_key_bytes);
-        
-        unparsed_key.verify(&hash, signature.as_ref())
+        verify_rsa_pss_sha256(root_public_key_bytes, &hash, signature.as_ref())
             .map_err(|e| IdentityError::InternalError(
                 format!("CRL signature verification failed: {}", e)
             ))?;
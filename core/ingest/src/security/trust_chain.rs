@@ -12,8 +12,8 @@
 
 use std::sync::Arc;
 use x509_parser::prelude::*;
-use ring::signature::{UnparsedPublicKey, RSA_PSS_2048_8192_SHA256};
-use sha2::{Sha256, Digest};
+use crypto::signature::verify_rsa_pss_sha256;
+use crypto::digest::Sha256;
 use tracing::{error, debug, warn};
 
 use crate::security::errors::IdentityError;
@@ -72,15 +72,13 @@ impl TrustChainValidator {
         let sig_alg = &cert.signature_algorithm;
         
         // Verify signature algorithm is RSA-PSS-SHA256
-        let algorithm = match sig_alg.algorithm.to_id_string().as_str() {
-            "1.2.840.113549.1.1.10" => &RSA_PSS_2048_8192_SHA256, // RSA-PSS
-            "1.2.840.113549.1.1.11" => &RSA_PSS_2048_8192_SHA256, // RSA-PSS with SHA-256 (approximate)
-            _ => {
-                warn!("Unsupported signature algorithm: {}, defaulting to RSA-PSS", sig_alg.algorithm.to_id_string());
-                // Default to RSA-PSS-SHA256
-                &RSA_PSS_2048_8192_SHA256
+        match sig_alg.algorithm.to_id_string().as_str() {
+            "1.2.840.113549.1.1.10" => {} // RSA-PSS
+            "1.2.840.113549.1.1.11" => {} // RSA-PSS with SHA-256 (approximate)
+            other => {
+                warn!("Unsupported signature algorithm: {}, defaulting to RSA-PSS", other);
             }
-        };
+        }
         
         // Get certificate's TBS (To Be Signed) certificate bytes
         // This is the certificate data that was signed
@@ -95,9 +93,7 @@ impl TrustChainValidator {
         let signature = &cert.signature_value;
         
         // Verify signature
-        let unparsed_key = UnparsedPublicKey::new(algorithm, issuer_public_key_bytes);
-        
-        unparsed_key.verify(&hash, signature.as_ref())
+        verify_rsa_pss_sha256(issuer_public_key_bytes, &hash, signature.as_ref())
             .map_err(|e| IdentityError::ChainValidationFailed(
                 format!("Certificate signature verification failed: {}", e)
             ))?;
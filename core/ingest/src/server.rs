@@ -25,6 +25,9 @@ use crate::dedupe::ContentDeduplicator;
 use crate::dispatcher::EventDispatcher;
 use crate::config::Config;
 use crate::security::{TrustStore, IdentityVerifier, TrustChainValidator, RevocationChecker, ReplayProtector};
+use crate::security::replay_protection::AUTH_REPLAY_SCOPE;
+use crate::shared_state::{self, LocalReplayState, PostgresReplayState, ReplayStateBackend, SharedStateConfig, SharedStateMode};
+use ingest::storage::postgres::connect_from_env;
 
 pub struct IngestionServer {
     config: Config,
@@ -50,7 +53,18 @@ impl IngestionServer {
         
         let trust_chain_validator = Arc::new(TrustChainValidator::new(trust_store.clone())?);
         let revocation_checker = Arc::new(RevocationChecker::new(trust_store.clone(), config.crl_path.clone())?);
-        let replay_protector = Arc::new(ReplayProtector::new()?);
+        // Replay state: per process, or shared with the other instances behind the load balancer
+        let shared_state = SharedStateConfig::from_env()?;
+        let replay_state: Arc<dyn ReplayStateBackend> = match shared_state.mode {
+            SharedStateMode::Local => Arc::new(LocalReplayState::new()),
+            SharedStateMode::Postgres => {
+                let db = connect_from_env().await?;
+                shared_state::spawn_purge(db.clone(), &shared_state);
+                Arc::new(PostgresReplayState::new(db))
+            }
+        };
+        info!("Shared ingest state: {}", shared_state.mode.as_str());
+        let replay_protector = Arc::new(ReplayProtector::with_state(replay_state.clone(), AUTH_REPLAY_SCOPE));
         
         let identity_verifier = Arc::new(IdentityVerifier::new(
             trust_store.clone(),
@@ -65,7 +79,7 @@ impl IngestionServer {
         let rate_limiter = Arc::new(RateLimiter::new(&config)?);
         let backpressure = Arc::new(BackpressureController::new(&config)?);
         let buffer = Arc::new(EventBuffer::new(&config)?);
-        let ordering = Arc::new(OrderingManager::new(&config)?);
+        let ordering = Arc::new(OrderingManager::with_replay_state(&config, replay_state));
         let deduplicator = Arc::new(ContentDeduplicator::new(&config)?);
         let dispatcher = Arc::new(EventDispatcher::new(&config)?);
         
@@ -0,0 +1,97 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/service_health.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Ingest health report in the shared health model - drain, pipeline, load shedding, spool disk quotas and orchestrator link as subsystems, dashboard cache counters as metrics, sent with every orchestrator heartbeat and served on GET /admin/health
+
+use health::{HealthReport, HealthStatus};
+
+use crate::dashboard_cache::DashboardCache;
+use crate::disk_quota::DiskQuotas;
+use crate::handoff::DrainController;
+use crate::load_shedding::LoadShedStats;
+use crate::pipeline::PipelineStats;
+use crate::service_heartbeat::{OrchestratorLinkStatus, INGEST_SERVICE_NAME};
+
+/// Health of this ingest instance.
+///
+/// - `drain`: unhealthy once a drain has started (the instance stops accepting work)
+/// - `pipeline`: unhealthy before the shards start, degraded while a shard queue is full
+/// - `load_shedding`: degraded while low-priority events or slow agents are shed
+/// - `disk_quota`: degraded while a spool is past its warning level, unhealthy at its budget
+/// - `orchestrator_link`: degraded while heartbeats are not acknowledged (ingestion continues)
+///
+/// Dashboard cache hits, misses and invalidations are reported as metrics only.
+pub fn report(
+    drain: &DrainController,
+    pipeline: &PipelineStats,
+    load_shedding: &LoadShedStats,
+    disk_quotas: &DiskQuotas,
+    dashboard: &DashboardCache,
+    link: &OrchestratorLinkStatus,
+) -> HealthReport {
+    let mut report = HealthReport::new(INGEST_SERVICE_NAME, link.instance_id.clone());
+
+    if drain.is_draining() {
+        report.push_subsystem(
+            "drain",
+            HealthStatus::Unhealthy,
+            Some(format!("draining: {}", drain.reason().unwrap_or_default())),
+        );
+    } else {
+        report.push_subsystem("drain", HealthStatus::Healthy, None);
+    }
+
+    let full: Vec<usize> =
+        pipeline.per_shard.iter().filter(|s| s.queued >= pipeline.queue_depth).map(|s| s.shard).collect();
+    if !pipeline.started {
+        report.push_subsystem("pipeline", HealthStatus::Unhealthy, Some("shards not started".to_string()));
+    } else if !full.is_empty() {
+        report.push_subsystem(
+            "pipeline",
+            HealthStatus::Degraded,
+            Some(format!("{} of {} shard queue(s) full", full.len(), pipeline.shards)),
+        );
+    } else {
+        report.push_subsystem("pipeline", HealthStatus::Healthy, None);
+    }
+
+    if load_shedding.mode == "off" {
+        report.push_subsystem("load_shedding", HealthStatus::Healthy, Some("disabled".to_string()));
+    } else if load_shedding.shedding {
+        report.push_subsystem(
+            "load_shedding",
+            HealthStatus::Degraded,
+            Some(format!("shedding ({}): average unit of work {}ms", load_shedding.mode, pipeline.latency_us / 1000)),
+        );
+    } else {
+        report.push_subsystem("load_shedding", HealthStatus::Healthy, None);
+    }
+
+    disk_quotas.push_health(&mut report);
+
+    if !link.enabled {
+        report.push_subsystem("orchestrator_link", HealthStatus::Healthy, Some("disabled".to_string()));
+    } else if !link.connected {
+        let detail = match &link.last_error {
+            Some(err) => format!("not acknowledged ({} consecutive failures, last: {})", link.consecutive_failures, err),
+            None => "not acknowledged yet".to_string(),
+        };
+        report.push_subsystem("orchestrator_link", HealthStatus::Degraded, Some(detail));
+    } else {
+        report.push_subsystem("orchestrator_link", HealthStatus::Healthy, None);
+    }
+
+    report.set_metric("in_flight_requests", drain.in_flight() as f64);
+    report.set_metric("pipeline_queued", pipeline.per_shard.iter().map(|s| s.queued).sum::<usize>() as f64);
+    report.set_metric("pipeline_processed", pipeline.per_shard.iter().map(|s| s.processed).sum::<u64>() as f64);
+    report.set_metric("pipeline_refused", pipeline.per_shard.iter().map(|s| s.refused).sum::<u64>() as f64);
+    report.set_metric("pipeline_backlog_queued", pipeline.per_shard.iter().map(|s| s.backlog_queued).sum::<usize>() as f64);
+    report.set_metric("pipeline_latency_us", pipeline.latency_us as f64);
+    report.set_metric("load_shed_relaxed", (load_shedding.relaxed_low_priority + load_shedding.relaxed_slow_agent) as f64);
+    report.set_metric("load_shed_rejected", (load_shedding.rejected_low_priority + load_shedding.rejected_slow_agent) as f64);
+    report.set_metric("orchestrator_consecutive_failures", link.consecutive_failures as f64);
+    let dashboard = dashboard.stats();
+    report.set_metric("dashboard_cache_hits", dashboard.queries.iter().map(|q| q.hits).sum::<u64>() as f64);
+    report.set_metric("dashboard_cache_misses", dashboard.queries.iter().map(|q| q.misses).sum::<u64>() as f64);
+    report.set_metric("dashboard_cache_invalidations", dashboard.queries.iter().map(|q| q.invalidations).sum::<u64>() as f64);
+    report
+}
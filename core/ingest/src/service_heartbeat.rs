@@ -0,0 +1,451 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/service_heartbeat.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Service registration/heartbeat protocol with the orchestrator - wire types, the periodic heartbeat client and the service-side view of the orchestrator link
+
+/*
+ * Service Heartbeat
+ *
+ * Core services that run as separate binaries (ingest first) report to the orchestrator with
+ * POST /v1/services/heartbeat every RANSOMEYE_SERVICE_HEARTBEAT_INTERVAL_SECS. The body names the
+ * service, its component instance (hostname, as in ransomeye.components), a per-process boot_id
+ * and the service's own readiness. The orchestrator answers with its instance, lifecycle state and
+ * health, so liveness is checked in both directions:
+ *
+ *   - the orchestrator marks a service stale when heartbeats stop, and holds its health gate
+ *     until every RANSOMEYE_REQUIRED_SERVICES entry has reported ready
+ *   - the service tracks the last acknowledgement and reports the orchestrator lost after
+ *     RANSOMEYE_ORCHESTRATOR_LOST_AFTER_SECS without one (and restarted when its instance changes)
+ *
+ * Heartbeats are enabled by RANSOMEYE_ORCHESTRATOR_URL. The shared bearer token
+ * (RANSOMEYE_SERVICE_HEARTBEAT_TOKEN_PATH) must match the orchestrator's. Losing the orchestrator
+ * never stops ingestion; it is logged and exposed on GET /admin/orchestrator-link.
+ */
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use health::HealthReport;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+pub const HEARTBEAT_PATH: &str = "/v1/services/heartbeat";
+pub const STATUS_PATH: &str = "/v1/status";
+
+/// Service name the ingest server registers under (matches its ransomeye.components row).
+pub const INGEST_SERVICE_NAME: &str = "ransomeye_ingestion";
+
+const MAX_NAME_LEN: usize = 64;
+const MAX_INSTANCE_LEN: usize = 128;
+const MAX_INTERVAL_SECS: u64 = 3600;
+const MAX_ERROR_LEN: usize = 256;
+
+/// Readiness a service reports about itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ServiceStatus {
+    Starting,
+    Ready,
+    Degraded,
+    Draining,
+}
+
+impl ServiceStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ServiceStatus::Starting => "starting",
+            ServiceStatus::Ready => "ready",
+            ServiceStatus::Degraded => "degraded",
+            ServiceStatus::Draining => "draining",
+        }
+    }
+
+    /// Serving traffic (degraded still counts; starting and draining do not).
+    pub fn is_serving(&self) -> bool {
+        matches!(self, ServiceStatus::Ready | ServiceStatus::Degraded)
+    }
+}
+
+/// POST /v1/services/heartbeat body.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Heartbeat {
+    pub service: String,
+    /// Component instance (hostname); together with `service` it keys the components row.
+    pub instance_id: String,
+    /// New for every process start; a change means the service restarted.
+    pub boot_id: Uuid,
+    pub version: Option<String>,
+    pub pid: u32,
+    pub status: ServiceStatus,
+    /// Interval the service sends at, so the orchestrator can report late heartbeats.
+    pub interval_secs: u64,
+    pub sent_at: DateTime<Utc>,
+    #[serde(default)]
+    pub details: Option<JsonValue>,
+    /// Build provenance (build_info::BuildInfo::metadata); recorded on the components row
+    #[serde(default)]
+    pub build: Option<JsonValue>,
+    /// Subsystem health in the shared model; recorded in component_health
+    #[serde(default)]
+    pub health: Option<HealthReport>,
+}
+
+impl Heartbeat {
+    /// Reject malformed registrations before they reach the registry.
+    pub fn validate(&self) -> Result<(), String> {
+        let name_ok = !self.service.is_empty()
+            && self.service.len() <= MAX_NAME_LEN
+            && self.service.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_' || b == b'-');
+        if !name_ok {
+            return Err(format!("invalid service name '{}'", self.service));
+        }
+        if self.instance_id.is_empty() || self.instance_id.len() > MAX_INSTANCE_LEN {
+            return Err("instance_id must be 1..=128 bytes".to_string());
+        }
+        if self.interval_secs == 0 || self.interval_secs > MAX_INTERVAL_SECS {
+            return Err(format!("interval_secs must be in 1..={}", MAX_INTERVAL_SECS));
+        }
+        Ok(())
+    }
+}
+
+/// Orchestrator's answer to a heartbeat.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct HeartbeatAck {
+    /// New for every orchestrator process start.
+    pub orchestrator_instance: Uuid,
+    /// Orchestrator lifecycle state (e.g. RUNNING).
+    pub orchestrator_state: String,
+    pub orchestrator_healthy: bool,
+    /// This service is in RANSOMEYE_REQUIRED_SERVICES.
+    pub required: bool,
+    pub received_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct HeartbeatConfig {
+    /// Orchestrator base URL (RANSOMEYE_ORCHESTRATOR_URL)
+    pub orchestrator_url: String,
+    pub token: Option<String>,
+    pub interval: Duration,
+    /// No acknowledgement for this long = orchestrator lost
+    pub lost_after: Duration,
+    pub request_timeout: Duration,
+}
+
+fn env_u64(key: &str, default_value: u64) -> Result<u64, String> {
+    match std::env::var(key) {
+        Ok(v) => v
+            .parse::<u64>()
+            .ok()
+            .filter(|n| *n > 0)
+            .ok_or_else(|| format!("Invalid {} '{}' (expected integer > 0)", key, v)),
+        Err(_) => Ok(default_value),
+    }
+}
+
+impl HeartbeatConfig {
+    /// None when RANSOMEYE_ORCHESTRATOR_URL is unset (heartbeats disabled).
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let orchestrator_url = match std::env::var("RANSOMEYE_ORCHESTRATOR_URL") {
+            Ok(v) if !v.trim().is_empty() => v.trim().trim_end_matches('/').to_string(),
+            _ => return Ok(None),
+        };
+        let parsed = url::Url::parse(&orchestrator_url)
+            .map_err(|e| format!("Invalid RANSOMEYE_ORCHESTRATOR_URL '{}': {}", orchestrator_url, e))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(format!("Invalid RANSOMEYE_ORCHESTRATOR_URL '{}': expected http or https", orchestrator_url));
+        }
+
+        let token = match std::env::var("RANSOMEYE_SERVICE_HEARTBEAT_TOKEN_PATH") {
+            Ok(path) => Some(read_token(&path)?),
+            Err(_) => None,
+        };
+
+        let interval_secs = env_u64("RANSOMEYE_SERVICE_HEARTBEAT_INTERVAL_SECS", 10)?;
+        if interval_secs > MAX_INTERVAL_SECS {
+            return Err(format!("RANSOMEYE_SERVICE_HEARTBEAT_INTERVAL_SECS must be <= {}", MAX_INTERVAL_SECS));
+        }
+        let lost_after_secs = env_u64("RANSOMEYE_ORCHESTRATOR_LOST_AFTER_SECS", interval_secs * 3)?;
+        if lost_after_secs <= interval_secs {
+            return Err(format!(
+                "RANSOMEYE_ORCHESTRATOR_LOST_AFTER_SECS ({}) must exceed the heartbeat interval ({})",
+                lost_after_secs, interval_secs
+            ));
+        }
+
+        Ok(Some(Self {
+            orchestrator_url,
+            token,
+            interval: Duration::from_secs(interval_secs),
+            lost_after: Duration::from_secs(lost_after_secs),
+            request_timeout: Duration::from_secs(interval_secs.min(5)),
+        }))
+    }
+
+    pub fn heartbeat_url(&self) -> String {
+        format!("{}{}", self.orchestrator_url, HEARTBEAT_PATH)
+    }
+}
+
+/// Shared heartbeat token file (trailing whitespace ignored) - FAIL-CLOSED if unreadable or short.
+pub fn read_token(path: &str) -> Result<String, String> {
+    let raw = std::fs::read_to_string(path)
+        .map_err(|e| format!("FAIL-CLOSED: cannot read heartbeat token {}: {}", path, e))?;
+    let token = raw.trim().to_string();
+    if token.len() < 16 {
+        return Err(format!("FAIL-CLOSED: heartbeat token {} must be at least 16 bytes", path));
+    }
+    Ok(token)
+}
+
+/// What changed on the link after an acknowledgement or a failed heartbeat.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkEvent {
+    Connected { orchestrator_instance: Uuid },
+    Restarted { previous: Uuid, current: Uuid },
+    Recovered { orchestrator_instance: Uuid },
+    Lost { silent_secs: u64 },
+}
+
+/// GET /admin/orchestrator-link response.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct OrchestratorLinkStatus {
+    /// False when RANSOMEYE_ORCHESTRATOR_URL is unset
+    pub enabled: bool,
+    pub orchestrator_url: Option<String>,
+    pub service: String,
+    pub instance_id: String,
+    pub boot_id: Uuid,
+    /// Acknowledged within RANSOMEYE_ORCHESTRATOR_LOST_AFTER_SECS
+    pub connected: bool,
+    pub orchestrator_instance: Option<Uuid>,
+    pub orchestrator_state: Option<String>,
+    pub orchestrator_healthy: Option<bool>,
+    pub last_ack_age_secs: Option<u64>,
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct LinkInner {
+    last_ack: Option<HeartbeatAck>,
+    last_ack_at: Option<Instant>,
+    consecutive_failures: u32,
+    last_error: Option<String>,
+    lost: bool,
+}
+
+/// This service's view of the orchestrator.
+#[derive(Debug)]
+pub struct OrchestratorLink {
+    service: String,
+    instance_id: String,
+    boot_id: Uuid,
+    orchestrator_url: Option<String>,
+    lost_after: Duration,
+    started_at: Instant,
+    inner: Mutex<LinkInner>,
+}
+
+impl OrchestratorLink {
+    pub fn new(service: &str, instance_id: &str, cfg: Option<&HeartbeatConfig>) -> Self {
+        Self {
+            service: service.to_string(),
+            instance_id: instance_id.to_string(),
+            boot_id: Uuid::new_v4(),
+            orchestrator_url: cfg.map(|c| c.orchestrator_url.clone()),
+            lost_after: cfg.map(|c| c.lost_after).unwrap_or(Duration::MAX),
+            started_at: Instant::now(),
+            inner: Mutex::new(LinkInner::default()),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.orchestrator_url.is_some()
+    }
+
+    pub fn service(&self) -> &str {
+        &self.service
+    }
+
+    pub fn instance_id(&self) -> &str {
+        &self.instance_id
+    }
+
+    pub fn boot_id(&self) -> Uuid {
+        self.boot_id
+    }
+
+    /// Record an acknowledgement received at `now`.
+    pub fn record_ack(&self, ack: HeartbeatAck, now: Instant) -> Option<LinkEvent> {
+        let mut inner = self.inner.lock();
+        let current = ack.orchestrator_instance;
+        let event = match (&inner.last_ack, inner.lost) {
+            (None, _) => Some(LinkEvent::Connected { orchestrator_instance: current }),
+            (Some(prev), _) if prev.orchestrator_instance != current => Some(LinkEvent::Restarted {
+                previous: prev.orchestrator_instance,
+                current,
+            }),
+            (Some(_), true) => Some(LinkEvent::Recovered { orchestrator_instance: current }),
+            (Some(_), false) => None,
+        };
+        inner.last_ack = Some(ack);
+        inner.last_ack_at = Some(now);
+        inner.consecutive_failures = 0;
+        inner.last_error = None;
+        inner.lost = false;
+        event
+    }
+
+    /// Record a failed heartbeat; reports `Lost` once when the silence exceeds `lost_after`.
+    pub fn record_failure(&self, err: &str, now: Instant) -> Option<LinkEvent> {
+        let mut inner = self.inner.lock();
+        inner.consecutive_failures = inner.consecutive_failures.saturating_add(1);
+        inner.last_error = Some(truncate(err));
+        let silent = now.saturating_duration_since(inner.last_ack_at.unwrap_or(self.started_at));
+        if !inner.lost && silent >= self.lost_after {
+            inner.lost = true;
+            return Some(LinkEvent::Lost { silent_secs: silent.as_secs() });
+        }
+        None
+    }
+
+    pub fn status(&self, now: Instant) -> OrchestratorLinkStatus {
+        let inner = self.inner.lock();
+        let age = inner.last_ack_at.map(|at| now.saturating_duration_since(at));
+        OrchestratorLinkStatus {
+            enabled: self.enabled(),
+            orchestrator_url: self.orchestrator_url.clone(),
+            service: self.service.clone(),
+            instance_id: self.instance_id.clone(),
+            boot_id: self.boot_id,
+            connected: age.map(|a| a < self.lost_after).unwrap_or(false),
+            orchestrator_instance: inner.last_ack.as_ref().map(|a| a.orchestrator_instance),
+            orchestrator_state: inner.last_ack.as_ref().map(|a| a.orchestrator_state.clone()),
+            orchestrator_healthy: inner.last_ack.as_ref().map(|a| a.orchestrator_healthy),
+            last_ack_age_secs: age.map(|a| a.as_secs()),
+            consecutive_failures: inner.consecutive_failures,
+            last_error: inner.last_error.clone(),
+        }
+    }
+
+    /// Heartbeat body for the current moment.
+    pub fn heartbeat(&self, status: ServiceStatus, interval: Duration, details: Option<JsonValue>) -> Heartbeat {
+        Heartbeat {
+            service: self.service.clone(),
+            instance_id: self.instance_id.clone(),
+            boot_id: self.boot_id,
+            version: Some(crate::BUILD_INFO.version.to_string()),
+            pid: std::process::id(),
+            status,
+            interval_secs: interval.as_secs().max(1),
+            sent_at: Utc::now(),
+            details,
+            build: Some(crate::BUILD_INFO.metadata()),
+            health: None,
+        }
+    }
+}
+
+fn truncate(err: &str) -> String {
+    let mut out = err.to_string();
+    if out.len() > MAX_ERROR_LEN {
+        let mut cut = MAX_ERROR_LEN;
+        while !out.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        out.truncate(cut);
+    }
+    out
+}
+
+/// Periodic heartbeat sender. `status` (readiness and health report) is sampled before every heartbeat.
+pub struct HeartbeatClient {
+    cfg: HeartbeatConfig,
+    link: Arc<OrchestratorLink>,
+    http: reqwest::Client,
+}
+
+impl HeartbeatClient {
+    pub fn new(cfg: HeartbeatConfig, link: Arc<OrchestratorLink>) -> Result<Self, String> {
+        let http = reqwest::Client::builder()
+            .timeout(cfg.request_timeout)
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .map_err(|e| format!("Failed to build heartbeat HTTP client: {e}"))?;
+        Ok(Self { cfg, link, http })
+    }
+
+    pub fn spawn<F>(self, status: F) -> tokio::task::JoinHandle<()>
+    where
+        F: Fn() -> (ServiceStatus, HealthReport) + Send + Sync + 'static,
+    {
+        tokio::spawn(async move {
+            info!(
+                "Orchestrator heartbeat started | url={} | service={} | instance={} | interval={}s",
+                self.cfg.heartbeat_url(),
+                self.link.service(),
+                self.link.instance_id(),
+                self.cfg.interval.as_secs()
+            );
+            let mut tick = tokio::time::interval(self.cfg.interval);
+            tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                tick.tick().await;
+                let (readiness, health) = status();
+                let event = match self.send_once(readiness, Some(health)).await {
+                    Ok(ack) => self.link.record_ack(ack, Instant::now()),
+                    Err(e) => {
+                        warn!("Orchestrator heartbeat failed: {}", e);
+                        self.link.record_failure(&e, Instant::now())
+                    }
+                };
+                match event {
+                    Some(LinkEvent::Connected { orchestrator_instance }) => {
+                        info!("Registered with orchestrator | orchestrator_instance={}", orchestrator_instance)
+                    }
+                    Some(LinkEvent::Restarted { previous, current }) => warn!(
+                        "Orchestrator restarted | previous_instance={} | current_instance={}",
+                        previous, current
+                    ),
+                    Some(LinkEvent::Recovered { orchestrator_instance }) => {
+                        info!("Orchestrator reachable again | orchestrator_instance={}", orchestrator_instance)
+                    }
+                    Some(LinkEvent::Lost { silent_secs }) => error!(
+                        "Orchestrator LOST: no heartbeat acknowledgement for {}s (ingestion continues)",
+                        silent_secs
+                    ),
+                    None => {}
+                }
+            }
+        })
+    }
+
+    /// One heartbeat round trip.
+    pub async fn send_once(&self, status: ServiceStatus, health: Option<HealthReport>) -> Result<HeartbeatAck, String> {
+        let mut heartbeat = self.link.heartbeat(status, self.cfg.interval, None);
+        heartbeat.health = health;
+        let body = serde_json::to_vec(&heartbeat)
+            .map_err(|e| format!("Failed to serialize heartbeat: {e}"))?;
+        let mut req = self
+            .http
+            .post(self.cfg.heartbeat_url())
+            .header("content-type", "application/json")
+            .body(body);
+        if let Some(token) = &self.cfg.token {
+            req = req.bearer_auth(token);
+        }
+        let resp = req.send().await.map_err(|e| format!("request failed: {e}"))?;
+        let code = resp.status();
+        if !code.is_success() {
+            return Err(format!("HTTP {}", code.as_u16()));
+        }
+        let bytes = resp.bytes().await.map_err(|e| format!("failed to read acknowledgement: {e}"))?;
+        serde_json::from_slice(&bytes).map_err(|e| format!("invalid acknowledgement: {e}"))
+    }
+}
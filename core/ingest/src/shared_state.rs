@@ -0,0 +1,367 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/shared_state.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Replay-protection and rate-budget state shared by every ingest instance - per-producer sequence/nonce tracking and per-component budget windows kept in Postgres with single-statement upserts, or in process memory for a single instance
+
+/*
+ * Shared Ingest State
+ *
+ * A load balancer may send consecutive events of one producer to different ingest instances.
+ * State kept in one instance's memory then misses replays seen by another instance and gives
+ * every instance its own full rate budget. RANSOMEYE_INGEST_SHARED_STATE selects where it lives:
+ *
+ *   local     process memory (default; a single instance)
+ *   postgres  ingest_replay_state / ingest_replay_nonces / ingest_rate_windows, shared by all
+ *             instances on the same database
+ *
+ * Every check is one statement, so concurrent instances serialize on the producer's (or
+ * component's) row and never need an explicit transaction:
+ *
+ *   replay  upsert the producer row only if sequence and timestamp do not go backwards, then
+ *           insert the nonce only if the row advanced; a conflicting nonce is a replay
+ *   budget  upsert the component's window, restarting it once it is BUDGET_WINDOW old (database
+ *           clock, so instance clocks do not matter) and returning the new count
+ *
+ * A failed check rejects the event (FAIL-CLOSED). Nonces past the TTL and idle windows are purged
+ * every RANSOMEYE_INGEST_SHARED_STATE_PURGE_SECS.
+ */
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use tokio_postgres::Client;
+use tracing::{info, warn};
+
+/// Nonces are remembered this long (also the replay window of a producer)
+pub const NONCE_TTL: Duration = Duration::from_secs(24 * 3600);
+
+/// Budget windows idle this long are purged
+const WINDOW_IDLE_RETENTION: Duration = Duration::from_secs(3600);
+
+const DEFAULT_PURGE_SECS: u64 = 300;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SharedStateMode {
+    Local,
+    Postgres,
+}
+
+impl SharedStateMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SharedStateMode::Local => "local",
+            SharedStateMode::Postgres => "postgres",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SharedStateConfig {
+    pub mode: SharedStateMode,
+    pub purge_interval: Duration,
+}
+
+impl SharedStateConfig {
+    pub fn from_env() -> Result<Self, String> {
+        let mode = match std::env::var("RANSOMEYE_INGEST_SHARED_STATE").as_deref() {
+            Err(_) | Ok("local") => SharedStateMode::Local,
+            Ok("postgres") => SharedStateMode::Postgres,
+            Ok(other) => {
+                return Err(format!("Invalid RANSOMEYE_INGEST_SHARED_STATE '{}' (expected local or postgres)", other))
+            }
+        };
+        let purge_secs = match std::env::var("RANSOMEYE_INGEST_SHARED_STATE_PURGE_SECS") {
+            Ok(v) => v
+                .parse::<u64>()
+                .ok()
+                .filter(|n| *n > 0)
+                .ok_or_else(|| format!("Invalid RANSOMEYE_INGEST_SHARED_STATE_PURGE_SECS '{}'", v))?,
+            Err(_) => DEFAULT_PURGE_SECS,
+        };
+        Ok(Self { mode, purge_interval: Duration::from_secs(purge_secs) })
+    }
+}
+
+/// Outcome of recording one (nonce, sequence, timestamp) of a producer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayVerdict {
+    Accepted {
+        /// Sequence accepted before this one; None for a producer seen for the first time
+        previous_sequence: Option<u64>,
+    },
+    DuplicateNonce,
+    SequenceRegression { last_sequence: u64 },
+    TimestampRegression { last_timestamp: DateTime<Utc> },
+}
+
+/// Where per-producer replay state lives. `scope` separates independent checks of the same events.
+#[async_trait]
+pub trait ReplayStateBackend: Send + Sync {
+    async fn record(
+        &self,
+        scope: &str,
+        producer_id: &str,
+        nonce: &str,
+        sequence_number: u64,
+        timestamp: DateTime<Utc>,
+    ) -> Result<ReplayVerdict, String>;
+
+    async fn last_sequence_number(&self, scope: &str, producer_id: &str) -> Result<Option<u64>, String>;
+
+    fn mode(&self) -> SharedStateMode;
+}
+
+#[derive(Clone)]
+struct ProducerState {
+    last_sequence_number: u64,
+    last_timestamp: DateTime<Utc>,
+}
+
+/// Process-memory replay state (one instance).
+#[derive(Default)]
+pub struct LocalReplayState {
+    nonces: DashMap<(String, String), DashMap<String, DateTime<Utc>>>,
+    producers: DashMap<(String, String), ProducerState>,
+}
+
+impl LocalReplayState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl ReplayStateBackend for LocalReplayState {
+    async fn record(
+        &self,
+        scope: &str,
+        producer_id: &str,
+        nonce: &str,
+        sequence_number: u64,
+        timestamp: DateTime<Utc>,
+    ) -> Result<ReplayVerdict, String> {
+        let key = (scope.to_string(), producer_id.to_string());
+        let nonces = self.nonces.entry(key.clone()).or_default();
+        if nonces.contains_key(nonce) {
+            return Ok(ReplayVerdict::DuplicateNonce);
+        }
+
+        let previous_sequence = match self.producers.entry(key) {
+            dashmap::mapref::entry::Entry::Vacant(v) => {
+                v.insert(ProducerState { last_sequence_number: sequence_number, last_timestamp: timestamp });
+                None
+            }
+            dashmap::mapref::entry::Entry::Occupied(mut o) => {
+                let state = o.get_mut();
+                if sequence_number < state.last_sequence_number {
+                    return Ok(ReplayVerdict::SequenceRegression { last_sequence: state.last_sequence_number });
+                }
+                if timestamp < state.last_timestamp {
+                    return Ok(ReplayVerdict::TimestampRegression { last_timestamp: state.last_timestamp });
+                }
+                let previous = state.last_sequence_number;
+                *state = ProducerState { last_sequence_number: sequence_number, last_timestamp: timestamp };
+                Some(previous)
+            }
+        };
+
+        nonces.insert(nonce.to_string(), timestamp);
+        let ttl = chrono::Duration::from_std(NONCE_TTL).unwrap_or(chrono::Duration::hours(24));
+        let now = Utc::now();
+        nonces.retain(|_, seen| now - *seen <= ttl);
+        Ok(ReplayVerdict::Accepted { previous_sequence })
+    }
+
+    async fn last_sequence_number(&self, scope: &str, producer_id: &str) -> Result<Option<u64>, String> {
+        Ok(self
+            .producers
+            .get(&(scope.to_string(), producer_id.to_string()))
+            .map(|s| s.last_sequence_number))
+    }
+
+    fn mode(&self) -> SharedStateMode {
+        SharedStateMode::Local
+    }
+}
+
+/// Replay state in Postgres, shared by every instance on the database.
+pub struct PostgresReplayState {
+    db: Arc<Client>,
+}
+
+impl PostgresReplayState {
+    pub fn new(db: Arc<Client>) -> Self {
+        Self { db }
+    }
+}
+
+fn to_i64(sequence_number: u64) -> Result<i64, String> {
+    i64::try_from(sequence_number).map_err(|_| format!("sequence number {} out of range", sequence_number))
+}
+
+#[async_trait]
+impl ReplayStateBackend for PostgresReplayState {
+    async fn record(
+        &self,
+        scope: &str,
+        producer_id: &str,
+        nonce: &str,
+        sequence_number: u64,
+        timestamp: DateTime<Utc>,
+    ) -> Result<ReplayVerdict, String> {
+        let sequence = to_i64(sequence_number)?;
+        // The producer row only moves forward; the nonce is only claimed when it did
+        let row = self
+            .db
+            .query_one(
+                r#"
+                WITH prior AS (
+                    SELECT last_sequence FROM ingest_replay_state WHERE scope = $1 AND producer_id = $2
+                ), advanced AS (
+                    INSERT INTO ingest_replay_state AS s (scope, producer_id, last_sequence, last_observed_at)
+                    VALUES ($1, $2, $4, $5)
+                    ON CONFLICT (scope, producer_id) DO UPDATE
+                        SET last_sequence = EXCLUDED.last_sequence,
+                            last_observed_at = EXCLUDED.last_observed_at,
+                            updated_at = now()
+                        WHERE s.last_sequence <= EXCLUDED.last_sequence
+                          AND s.last_observed_at <= EXCLUDED.last_observed_at
+                    RETURNING 1
+                ), claimed AS (
+                    INSERT INTO ingest_replay_nonces (scope, producer_id, nonce, observed_at)
+                    SELECT $1, $2, $3, $5 WHERE EXISTS (SELECT 1 FROM advanced)
+                    ON CONFLICT (scope, producer_id, nonce) DO NOTHING
+                    RETURNING 1
+                )
+                SELECT EXISTS (SELECT 1 FROM advanced), EXISTS (SELECT 1 FROM claimed),
+                       (SELECT last_sequence FROM prior)
+                "#,
+                &[&scope, &producer_id, &nonce, &sequence, &timestamp],
+            )
+            .await
+            .map_err(|e| format!("Failed to record replay state: {}", e))?;
+        let advanced: bool = row.get(0);
+        let claimed: bool = row.get(1);
+        let prior: Option<i64> = row.get(2);
+
+        if advanced && claimed {
+            return Ok(ReplayVerdict::Accepted { previous_sequence: prior.map(|p| p.max(0) as u64) });
+        }
+        if advanced {
+            return Ok(ReplayVerdict::DuplicateNonce);
+        }
+        // Rejected by the WHERE: report which side went backwards
+        let current = self
+            .db
+            .query_one(
+                "SELECT last_sequence, last_observed_at FROM ingest_replay_state WHERE scope = $1 AND producer_id = $2",
+                &[&scope, &producer_id],
+            )
+            .await
+            .map_err(|e| format!("Failed to read replay state: {}", e))?;
+        let last_sequence: i64 = current.get(0);
+        let last_timestamp: DateTime<Utc> = current.get(1);
+        if sequence < last_sequence {
+            Ok(ReplayVerdict::SequenceRegression { last_sequence: last_sequence.max(0) as u64 })
+        } else {
+            Ok(ReplayVerdict::TimestampRegression { last_timestamp })
+        }
+    }
+
+    async fn last_sequence_number(&self, scope: &str, producer_id: &str) -> Result<Option<u64>, String> {
+        let row = self
+            .db
+            .query_opt(
+                "SELECT last_sequence FROM ingest_replay_state WHERE scope = $1 AND producer_id = $2",
+                &[&scope, &producer_id],
+            )
+            .await
+            .map_err(|e| format!("Failed to read replay state: {}", e))?;
+        Ok(row.map(|r| r.get::<_, i64>(0).max(0) as u64))
+    }
+
+    fn mode(&self) -> SharedStateMode {
+        SharedStateMode::Postgres
+    }
+}
+
+/// Budget window of one component instance after counting an event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowCount {
+    pub window_start: DateTime<Utc>,
+    /// Events counted in the window, including this one and any rejected ones
+    pub count: u64,
+}
+
+/// Per-component budget windows in Postgres, shared by every instance on the database.
+pub struct PostgresRateWindows {
+    db: Arc<Client>,
+}
+
+impl PostgresRateWindows {
+    pub fn new(db: Arc<Client>) -> Self {
+        Self { db }
+    }
+
+    /// Count one event of `component_id` in its current window of length `window`.
+    pub async fn count(&self, component_type: &str, component_id: &str, window: Duration) -> Result<WindowCount, String> {
+        let window_secs = window.as_secs_f64();
+        let row = self
+            .db
+            .query_one(
+                r#"
+                INSERT INTO ingest_rate_windows AS w (component_type, component_id, window_start, event_count)
+                VALUES ($1, $2, now(), 1)
+                ON CONFLICT (component_type, component_id) DO UPDATE
+                    SET window_start = CASE WHEN w.window_start <= now() - make_interval(secs => $3)
+                                            THEN now() ELSE w.window_start END,
+                        event_count = CASE WHEN w.window_start <= now() - make_interval(secs => $3)
+                                           THEN 1 ELSE w.event_count + 1 END
+                RETURNING window_start, event_count
+                "#,
+                &[&component_type, &component_id, &window_secs],
+            )
+            .await
+            .map_err(|e| format!("Failed to count ingest rate window: {}", e))?;
+        let count: i64 = row.get(1);
+        Ok(WindowCount { window_start: row.get(0), count: count.max(0) as u64 })
+    }
+}
+
+/// Remove nonces past NONCE_TTL and windows idle for an hour. Returns (nonces, windows) removed.
+pub async fn purge(db: &Client) -> Result<(u64, u64), String> {
+    let nonces = db
+        .execute(
+            "DELETE FROM ingest_replay_nonces WHERE observed_at < now() - make_interval(secs => $1)",
+            &[&NONCE_TTL.as_secs_f64()],
+        )
+        .await
+        .map_err(|e| format!("Failed to purge replay nonces: {}", e))?;
+    let windows = db
+        .execute(
+            "DELETE FROM ingest_rate_windows WHERE window_start < now() - make_interval(secs => $1)",
+            &[&WINDOW_IDLE_RETENTION.as_secs_f64()],
+        )
+        .await
+        .map_err(|e| format!("Failed to purge rate windows: {}", e))?;
+    Ok((nonces, windows))
+}
+
+/// Periodic purge; every instance runs it, the DELETEs are idempotent.
+pub fn spawn_purge(db: Arc<Client>, cfg: &SharedStateConfig) -> tokio::task::JoinHandle<()> {
+    let interval = cfg.purge_interval;
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(interval);
+        tick.tick().await;
+        loop {
+            tick.tick().await;
+            match purge(&db).await {
+                Ok((0, 0)) => {}
+                Ok((nonces, windows)) => info!("Purged {} replay nonce(s) and {} idle rate window(s)", nonces, windows),
+                Err(e) => warn!("Shared ingest state purge failed: {}", e),
+            }
+        }
+    })
+}
@@ -0,0 +1,327 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/signature_verification.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Ed25519 verification of signed events against a trust store of registered agent public keys (RANSOMEYE_TRUST_STORE_PATH/agent_keys or agents.signing_public_key), with enforce/detect/disabled modes
+
+/*
+ * Event Signature Verification
+ *
+ * Producers sign the SHA-256 of the canonical envelope (payload_hash) under their signer's
+ * sequence number: the signed message is sequence (u64, big endian) || hash bytes, and the
+ * sequence travels in SignedEvent.sequence. Events without a sequence are verified over the hash
+ * bytes alone. Producers embed the canonical envelope in the SignedEvent verbatim, so
+ * payload_hash must equal the SHA-256 of the envelope bytes as received; a mismatch is invalid
+ * before any key is consulted.
+ *
+ * The sequence is not checked for monotonicity: agents deliver by priority and replay their
+ * spool, so sequences legitimately arrive out of order. A replayed event repeats its message_id
+ * and is refused by the telemetry (source, message_id) uniqueness, and a captured signature
+ * cannot carry another envelope since it binds that envelope's hash.
+ *
+ * Key of a signer, first match wins:
+ *   1. <RANSOMEYE_TRUST_STORE_PATH>/agent_keys/<signer_id>.pub - operator-provisioned, read once
+ *      at startup (raw 32 bytes or hex)
+ *   2. agents.signing_public_key of the resolved agent - registered at enrollment, replaced by
+ *      operator-approved key rotation; cached per agent for KEY_CACHE_TTL
+ * A signer with neither is unknown and treated like a bad signature.
+ *
+ * Every result is recorded in signature_validation_events: accepted events in their own ingest
+ * transaction, rejected ones on their own.
+ */
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crypto::digest::Sha256;
+use crypto::signature::{verify_ed25519, ED25519_PUBLIC_KEY_LEN, ED25519_SIGNATURE_LEN};
+use dashmap::DashMap;
+use tokio_postgres::Client;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::suppression::parse_public_key;
+
+/// How long an agents.signing_public_key lookup is trusted before it is re-read.
+const KEY_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Subdirectory of the trust store holding agent public keys
+pub const AGENT_KEYS_DIR: &str = "agent_keys";
+
+pub type PublicKey = [u8; ED25519_PUBLIC_KEY_LEN];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureMode {
+    /// Events that do not verify are rejected (403) and recorded (default, fail-closed).
+    Enforce,
+    /// Events that do not verify are recorded but accepted (agent rollout).
+    Detect,
+    /// No verification (lab mode).
+    Disabled,
+}
+
+impl SignatureMode {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "enforce" => Ok(SignatureMode::Enforce),
+            "detect" => Ok(SignatureMode::Detect),
+            "disabled" => Ok(SignatureMode::Disabled),
+            other => Err(format!(
+                "Invalid RANSOMEYE_INGEST_SIGNATURE_VERIFICATION '{}' (expected enforce|detect|disabled)",
+                other
+            )),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SignatureMode::Enforce => "enforce",
+            SignatureMode::Detect => "detect",
+            SignatureMode::Disabled => "disabled",
+        }
+    }
+}
+
+/// signature_status label of a verification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureStatus {
+    Valid,
+    Invalid,
+    /// No key registered for the signer
+    Unknown,
+}
+
+impl SignatureStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SignatureStatus::Valid => "valid",
+            SignatureStatus::Invalid => "invalid",
+            SignatureStatus::Unknown => "unknown",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeySource {
+    TrustStore,
+    Agents,
+}
+
+impl KeySource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KeySource::TrustStore => "trust_store",
+            KeySource::Agents => "agents",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureCheck {
+    pub status: SignatureStatus,
+    pub key_source: Option<KeySource>,
+    pub error: Option<String>,
+}
+
+impl SignatureCheck {
+    pub fn is_valid(&self) -> bool {
+        self.status == SignatureStatus::Valid
+    }
+}
+
+struct CachedKey {
+    key: Option<PublicKey>,
+    loaded: Instant,
+}
+
+pub struct EventSignatureVerifier {
+    pub mode: SignatureMode,
+    trust_store: HashMap<String, PublicKey>,
+    db: Option<Arc<Client>>,
+    cache: DashMap<Uuid, CachedKey>,
+}
+
+impl EventSignatureVerifier {
+    /// Load from environment. FAIL-CLOSED: verification with no key source at all (no trust store
+    /// keys and no Postgres agents table) would reject every event, so startup aborts instead.
+    pub fn from_env(db: Option<Arc<Client>>) -> Result<Self, String> {
+        let mode = SignatureMode::parse(
+            &std::env::var("RANSOMEYE_INGEST_SIGNATURE_VERIFICATION").unwrap_or_else(|_| "enforce".to_string()),
+        )?;
+        if mode == SignatureMode::Disabled {
+            warn!("Event signature verification DISABLED (RANSOMEYE_INGEST_SIGNATURE_VERIFICATION=disabled)");
+            return Ok(Self::new(mode, HashMap::new(), None));
+        }
+
+        let trust_store_path = std::env::var("RANSOMEYE_TRUST_STORE_PATH")
+            .unwrap_or_else(|_| "/etc/ransomeye/trust_store".to_string());
+        let trust_store = load_trust_store(&Path::new(&trust_store_path).join(AGENT_KEYS_DIR))?;
+        if trust_store.is_empty() && db.is_none() {
+            return Err(format!(
+                "FAIL-CLOSED: signature verification has no agent keys ({}/{} is empty and the agents table needs the postgres backend); set RANSOMEYE_INGEST_SIGNATURE_VERIFICATION=disabled for lab mode",
+                trust_store_path, AGENT_KEYS_DIR
+            ));
+        }
+        info!(
+            "Event signature verification: mode={} | trust_store_keys={} | agents_table={}",
+            mode.as_str(),
+            trust_store.len(),
+            db.is_some()
+        );
+        Ok(Self::new(mode, trust_store, db))
+    }
+
+    pub fn new(mode: SignatureMode, trust_store: HashMap<String, PublicKey>, db: Option<Arc<Client>>) -> Self {
+        Self { mode, trust_store, db, cache: DashMap::new() }
+    }
+
+    /// Verify `signature` of `payload_hash` by `signer_id` (agent `agent_id`), with payload_hash
+    /// bound to the received `envelope` bytes. `Err` only when the agents table cannot be read.
+    pub async fn verify(
+        &self,
+        agent_id: Uuid,
+        signer_id: &str,
+        sequence: Option<u64>,
+        payload_hash: &str,
+        envelope: &[u8],
+        signature: &[u8],
+    ) -> Result<SignatureCheck, String> {
+        if let Err(error) = check_payload_hash(payload_hash, envelope) {
+            return Ok(SignatureCheck { status: SignatureStatus::Invalid, key_source: None, error: Some(error) });
+        }
+        let (key, source) = match self.trust_store.get(signer_id) {
+            Some(key) => (Some(*key), KeySource::TrustStore),
+            None => (self.agent_key(agent_id).await?, KeySource::Agents),
+        };
+        let Some(key) = key else {
+            return Ok(SignatureCheck {
+                status: SignatureStatus::Unknown,
+                key_source: None,
+                error: Some(format!("no public key registered for signer_id '{}'", signer_id)),
+            });
+        };
+        Ok(check(&key, source, sequence, payload_hash, signature))
+    }
+
+    /// Forget the cached key of an agent (enrollment or rotation on this instance).
+    pub fn invalidate(&self, agent_id: Uuid) {
+        self.cache.remove(&agent_id);
+    }
+
+    async fn agent_key(&self, agent_id: Uuid) -> Result<Option<PublicKey>, String> {
+        let Some(db) = &self.db else { return Ok(None) };
+        if let Some(cached) = self.cache.get(&agent_id) {
+            if cached.loaded.elapsed() < KEY_CACHE_TTL {
+                return Ok(cached.key);
+            }
+        }
+        let row = db
+            .query_opt("SELECT signing_public_key FROM agents WHERE agent_id = $1", &[&agent_id])
+            .await
+            .map_err(|e| format!("agents signing key lookup failed: {}", e))?;
+        let key = row
+            .and_then(|r| r.get::<_, Option<Vec<u8>>>(0))
+            .and_then(|k| PublicKey::try_from(k.as_slice()).ok());
+        self.cache.insert(agent_id, CachedKey { key, loaded: Instant::now() });
+        Ok(key)
+    }
+}
+
+/// Message a producer signs: sequence (big endian) || payload hash bytes.
+pub fn signed_message(sequence: Option<u64>, payload_hash: &str) -> Result<Vec<u8>, String> {
+    let hash = hex::decode(payload_hash).map_err(|_| "payload_hash is not hex".to_string())?;
+    let mut message = Vec::with_capacity(8 + hash.len());
+    if let Some(seq) = sequence {
+        message.extend_from_slice(&seq.to_be_bytes());
+    }
+    message.extend_from_slice(&hash);
+    Ok(message)
+}
+
+/// payload_hash must be the SHA-256 of the envelope bytes as received.
+pub fn check_payload_hash(payload_hash: &str, envelope: &[u8]) -> Result<(), String> {
+    let hash = hex::decode(payload_hash).map_err(|_| "payload_hash is not hex".to_string())?;
+    if hash.as_slice() != Sha256::digest(envelope).as_slice() {
+        return Err("payload_hash does not match the received envelope".to_string());
+    }
+    Ok(())
+}
+
+/// Verify against one key.
+pub fn check(key: &PublicKey, source: KeySource, sequence: Option<u64>, payload_hash: &str, signature: &[u8]) -> SignatureCheck {
+    let invalid = |error: String| SignatureCheck { status: SignatureStatus::Invalid, key_source: Some(source), error: Some(error) };
+    if signature.len() != ED25519_SIGNATURE_LEN {
+        return invalid(format!("signature is {} bytes, expected {}", signature.len(), ED25519_SIGNATURE_LEN));
+    }
+    let message = match signed_message(sequence, payload_hash) {
+        Ok(m) => m,
+        Err(e) => return invalid(e),
+    };
+    match verify_ed25519(key, &message, signature) {
+        Ok(()) => SignatureCheck { status: SignatureStatus::Valid, key_source: Some(source), error: None },
+        Err(_) => invalid("signature does not verify".to_string()),
+    }
+}
+
+/// Read `<dir>/<signer_id>.pub` files (raw 32-byte key or hex). A missing directory is an
+/// empty trust store; an unreadable or malformed key file aborts startup.
+pub fn load_trust_store(dir: &Path) -> Result<HashMap<String, PublicKey>, String> {
+    let mut keys = HashMap::new();
+    if !dir.exists() {
+        return Ok(keys);
+    }
+    let entries = std::fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+    for entry in entries {
+        let path = entry.map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("pub") {
+            continue;
+        }
+        let signer_id = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .ok_or_else(|| format!("Invalid agent key file name {}", path.display()))?
+            .to_string();
+        let bytes = std::fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let key = parse_key_file(&bytes).map_err(|e| format!("{}: {}", path.display(), e))?;
+        keys.insert(signer_id, key);
+    }
+    Ok(keys)
+}
+
+/// Key file contents: the raw 32-byte key (EventSigner::verifying_key) or its hex.
+pub fn parse_key_file(bytes: &[u8]) -> Result<PublicKey, String> {
+    if let Ok(key) = PublicKey::try_from(bytes) {
+        return Ok(key);
+    }
+    let text = std::str::from_utf8(bytes).map_err(|_| "not a raw or hex Ed25519 public key".to_string())?;
+    parse_public_key(text.trim())
+}
+
+/// Register the key presented at enrollment. Re-enrolling with the same key is a no-op; a
+/// different key is refused (`Ok(Some(existing))`) and must go through key rotation.
+pub async fn register_agent_key(db: &Client, agent_id: Uuid, key: &PublicKey) -> Result<Option<PublicKey>, String> {
+    let row = db
+        .query_one(
+            r#"
+            UPDATE agents SET signing_public_key = COALESCE(signing_public_key, $2), updated_at = NOW()
+            WHERE agent_id = $1
+            RETURNING signing_public_key
+            "#,
+            &[&agent_id, &key.as_slice()],
+        )
+        .await
+        .map_err(|e| format!("agents signing key registration failed: {}", e))?;
+    let stored: Vec<u8> = row.get(0);
+    Ok(if stored.as_slice() == key.as_slice() { None } else { PublicKey::try_from(stored.as_slice()).ok() })
+}
+
+/// Replace the agent's key (operator-approved rotation).
+pub async fn replace_agent_key(db: &Client, agent_id: Uuid, key: &PublicKey) -> Result<(), String> {
+    db.execute(
+        "UPDATE agents SET signing_public_key = $2, updated_at = NOW() WHERE agent_id = $1",
+        &[&agent_id, &key.as_slice()],
+    )
+    .await
+    .map_err(|e| format!("agents signing key rotation failed: {}", e))?;
+    Ok(())
+}
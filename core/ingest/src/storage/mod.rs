@@ -0,0 +1,690 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/storage/mod.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Pluggable telemetry storage - backend trait, record types, and config-driven backend selection (Postgres default, SQLite for edge/standalone lab mode)
+
+/*
+ * Telemetry Storage
+ *
+ * The ingest handlers persist through `TelemetryStore` instead of issuing SQL directly.
+ * A unit of work (audit + raw_events + typed telemetry) runs inside a `StorageTx` so the
+ * PROMPT-38.1 guarantee - raw event, telemetry and audit rows commit atomically - holds on
+ * every backend.
+ *
+ * Side effects of a unit of work (publishing to downstream consumers) are written to the
+ * ingest_outbox table inside the same StorageTx and relayed after commit (see outbox.rs).
+ *
+ * Lost telemetry is counted in telemetry_drops_daily: agent-reported drops are applied in the
+ * unit of work of the agent_stats event that carries them, ingest rejections are flushed from
+ * memory (see drop_accounting.rs).
+ *
+ * host_inventory holds the latest host facts per agent, written in the unit of work of the
+ * host_inventory event; an older report never replaces a newer one.
+ *
+ * The dashboard aggregates (detections per severity, events per minute, fleet summary) are read
+ * through a TTL cache (see dashboard_cache.rs), never per request.
+ *
+ * Backend is selected with RANSOMEYE_STORAGE_BACKEND:
+ *   postgres (default) - authoritative schema, hash-chained immutable_audit_log
+ *   sqlite             - single-file lab store (RANSOMEYE_SQLITE_PATH); no Postgres required
+ */
+
+pub mod postgres;
+pub mod sqlite;
+
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use serde_json::value::RawValue;
+use serde_json::Value as JsonValue;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::pcap_capture::PcapCaptureConfig;
+use crate::topology::ZoneLabel;
+
+pub use postgres::PostgresStore;
+pub use sqlite::SqliteStore;
+
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error("Storage configuration invalid: {0}")]
+    Config(String),
+    #[error("Storage connection failed: {0}")]
+    Connection(String),
+    #[error("Storage query failed: {0}")]
+    Query(String),
+    #[error("Invalid storage input: {0}")]
+    InvalidInput(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageBackend {
+    Postgres,
+    Sqlite,
+}
+
+impl StorageBackend {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StorageBackend::Postgres => "postgres",
+            StorageBackend::Sqlite => "sqlite",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StorageConfig {
+    pub backend: StorageBackend,
+    pub sqlite_path: PathBuf,
+    /// Packet captures queued for critical detections (Postgres only; None: off)
+    pub pcap_capture: Option<PcapCaptureConfig>,
+}
+
+impl StorageConfig {
+    pub fn from_env() -> Result<Self, StorageError> {
+        let backend = match std::env::var("RANSOMEYE_STORAGE_BACKEND")
+            .unwrap_or_else(|_| "postgres".to_string())
+            .as_str()
+        {
+            "postgres" => StorageBackend::Postgres,
+            "sqlite" => StorageBackend::Sqlite,
+            other => {
+                return Err(StorageError::Config(format!(
+                    "Invalid RANSOMEYE_STORAGE_BACKEND '{}' (expected postgres|sqlite)",
+                    other
+                )))
+            }
+        };
+        let sqlite_path = PathBuf::from(
+            std::env::var("RANSOMEYE_SQLITE_PATH")
+                .unwrap_or_else(|_| "/var/lib/ransomeye/ingest/telemetry.sqlite3".to_string()),
+        );
+        let pcap_capture = PcapCaptureConfig::from_env().map_err(StorageError::Config)?;
+        Ok(Self { backend, sqlite_path, pcap_capture })
+    }
+}
+
+/// Telemetry producer type (matches the Postgres `event_source_type` enum labels).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TelemetrySource {
+    LinuxAgent,
+    DpiProbe,
+}
+
+impl TelemetrySource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TelemetrySource::LinuxAgent => "linux_agent",
+            TelemetrySource::DpiProbe => "dpi_probe",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "linux_agent" => Some(TelemetrySource::LinuxAgent),
+            "dpi_probe" => Some(TelemetrySource::DpiProbe),
+            _ => None,
+        }
+    }
+}
+
+/// What raw_events.payload_json holds for a row (raw_events.payload_storage).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PayloadStorage {
+    /// Envelope exactly as received
+    Full,
+    /// Hashes plus extracted fields only; payload_sha256 still covers the full envelope
+    Summary,
+}
+
+impl PayloadStorage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PayloadStorage::Full => "full",
+            PayloadStorage::Summary => "summary",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "full" => Some(PayloadStorage::Full),
+            "summary" => Some(PayloadStorage::Summary),
+            _ => None,
+        }
+    }
+}
+
+/// Canonical append-only capture of an accepted envelope.
+#[derive(Debug, Clone)]
+pub struct RawEventRecord {
+    pub source: TelemetrySource,
+    pub agent_id: Uuid,
+    pub observed_at: DateTime<Utc>,
+    pub event_name: String,
+    /// Envelope JSON exactly as received (Full) or the summary document (Summary)
+    pub payload_json: Box<RawValue>,
+    /// SHA-256 of the received envelope bytes, regardless of payload_storage
+    pub payload_sha256: Vec<u8>,
+    /// Storage policy decision for this row and the rule that produced it
+    pub payload_storage: PayloadStorage,
+    pub payload_storage_reason: String,
+    /// Residency classification of the event's data (see residency.rs); None = unclassified
+    pub residency_region: Option<String>,
+    /// Site and zone of the sending sensor (see topology.rs); None = not placed
+    pub zone: Option<ZoneLabel>,
+}
+
+/// Source provenance shared by both telemetry tables.
+#[derive(Debug, Clone)]
+pub struct TelemetryProvenance {
+    pub agent_id: Uuid,
+    pub message_id: Uuid,
+    pub nonce: String,
+    pub component_identity: String,
+    pub signature_b64: String,
+    pub signature_alg: String,
+    pub data_hash_hex: String,
+    pub observed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LinuxTelemetry {
+    pub provenance: TelemetryProvenance,
+    pub host_id: String,
+    pub event_name: String,
+    pub event_category: String,
+    pub pid: Option<i32>,
+    pub uid: Option<i32>,
+    pub process_name: Option<String>,
+    pub cmdline: Option<String>,
+    pub file_path: Option<String>,
+    /// Already validated as IP addresses.
+    pub network_src_ip: Option<String>,
+    pub network_dst_ip: Option<String>,
+    pub network_src_port: Option<i32>,
+    pub network_dst_port: Option<i32>,
+    /// Network namespace of the socket and whether it is the host's
+    pub netns_inode: Option<i64>,
+    pub host_netns: Option<bool>,
+    pub protocol: Option<String>,
+    /// NULL = host activity
+    pub container_id: Option<String>,
+    pub container_image: Option<String>,
+    pub pod_name: Option<String>,
+    pub pod_namespace: Option<String>,
+    pub payload_json: String,
+    pub payload_sha256: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DpiTelemetry {
+    pub provenance: TelemetryProvenance,
+    /// Already validated as IP addresses.
+    pub src_ip: Option<String>,
+    pub src_port: Option<i32>,
+    pub dst_ip: Option<String>,
+    pub dst_port: Option<i32>,
+    pub protocol: Option<String>,
+    pub bytes_in: Option<i64>,
+    pub bytes_out: Option<i64>,
+    pub packets_in: Option<i64>,
+    pub packets_out: Option<i64>,
+    pub packet_count: Option<i64>,
+    pub byte_count: Option<i64>,
+    pub tls_sni: Option<String>,
+    pub ja3: Option<String>,
+    pub ja3s: Option<String>,
+    pub http_host: Option<String>,
+    pub http_method: Option<String>,
+    pub http_path: Option<String>,
+    pub iface_name: Option<String>,
+    pub flow_id: Option<String>,
+    /// metadata jsonb (mapped `metadata.<key>` fields), JSON text
+    pub metadata_json: Option<String>,
+    pub payload_json: String,
+    pub payload_sha256: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone)]
+pub enum TelemetryRecord {
+    Linux(LinuxTelemetry),
+    Dpi(DpiTelemetry),
+}
+
+/// One immutable audit entry; the backend computes the hash chain link.
+#[derive(Debug, Clone)]
+pub struct AuditRecord {
+    pub actor_component_id: Option<Uuid>,
+    pub actor_agent_id: Option<Uuid>,
+    pub action: String,
+    pub object_type: String,
+    pub object_id: Option<Uuid>,
+    pub event_time: Option<DateTime<Utc>>,
+    pub payload_json: JsonValue,
+    pub payload_sha256: Vec<u8>,
+}
+
+/// Detection raised by ingest itself (e.g. a component over its rate budget) - detection_results row.
+#[derive(Debug, Clone)]
+pub struct DetectionRecord {
+    pub detection_engine: String,
+    pub detection_name: String,
+    pub detection_category: Option<String>,
+    /// severity_level label (debug|info|notice|warning|error|critical)
+    pub severity: String,
+    pub confidence: f64,
+    pub reasoning: String,
+    pub artifacts: JsonValue,
+    /// 32-byte key for deduplication
+    pub deterministic_key: Vec<u8>,
+}
+
+/// Where an event came from: peer address and the producer-reported host id (envelope host_id).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct IdentityOrigin {
+    #[schema(value_type = Option<String>)]
+    pub source_ip: Option<IpAddr>,
+    pub host_id: Option<String>,
+}
+
+impl std::fmt::Display for IdentityOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ip = self.source_ip.map(|ip| ip.to_string());
+        write!(f, "source_ip={} host_id={}", ip.as_deref().unwrap_or("-"), self.host_id.as_deref().unwrap_or("-"))
+    }
+}
+
+/// One component identity seen from two origins - identity_conflicts row while open.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct IdentityConflictRecord {
+    pub conflict_id: Uuid,
+    pub component_type: String,
+    pub component_identity: String,
+    pub agent_id: Uuid,
+    pub first_origin: IdentityOrigin,
+    pub conflicting_origin: IdentityOrigin,
+    pub opened_at: DateTime<Utc>,
+}
+
+/// Operator decision for the events quarantined under a conflict.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictResolution {
+    /// Events were legitimate; rows are marked released for replay
+    Release,
+    /// Events came from the misconfigured host; rows are kept as evidence only
+    Discard,
+}
+
+impl ConflictResolution {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConflictResolution::Release => "release",
+            ConflictResolution::Discard => "discard",
+        }
+    }
+}
+
+/// Signed event held back while its identity is in conflict - quarantined_events row.
+#[derive(Debug, Clone)]
+pub struct QuarantinedEventRecord {
+    pub conflict_id: Uuid,
+    pub component_type: String,
+    pub component_identity: String,
+    pub message_id: String,
+    pub origin: IdentityOrigin,
+    /// Request body (signed event) exactly as received
+    pub signed_event: Box<RawValue>,
+    pub signed_event_sha256: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TelemetryQuery {
+    pub source: Option<TelemetrySource>,
+    pub agent_id: Option<Uuid>,
+    pub observed_from: Option<DateTime<Utc>>,
+    pub observed_to: Option<DateTime<Utc>>,
+    pub limit: i64,
+}
+
+/// Post-commit side effect written in the unit of work that causes it - ingest_outbox row.
+#[derive(Debug, Clone)]
+pub struct OutboxRecord {
+    pub topic: String,
+    /// Row the message is about (raw_events.raw_event_id for telemetry)
+    pub aggregate_id: Uuid,
+    pub payload: JsonValue,
+}
+
+/// Pending outbox message claimed by the relay.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OutboxEntry {
+    pub outbox_id: Uuid,
+    pub topic: String,
+    pub aggregate_id: Uuid,
+    pub payload: JsonValue,
+    /// Failed publish attempts so far
+    pub attempts: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Where telemetry was lost: on the agent before delivery, or rejected by ingest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DropOrigin {
+    Agent,
+    Ingest,
+}
+
+impl DropOrigin {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DropOrigin::Agent => "agent",
+            DropOrigin::Ingest => "ingest",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "agent" => Some(DropOrigin::Agent),
+            "ingest" => Some(DropOrigin::Ingest),
+            _ => None,
+        }
+    }
+}
+
+/// Events lost for one reason, added to the telemetry_drops_daily row of (agent, day, origin, reason).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DropCount {
+    pub agent_id: Uuid,
+    pub day: NaiveDate,
+    pub origin: DropOrigin,
+    pub reason: String,
+    pub dropped: u64,
+}
+
+/// One telemetry_drops_daily row.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct DropSummary {
+    pub agent_id: Uuid,
+    pub day: NaiveDate,
+    pub origin: DropOrigin,
+    pub reason: String,
+    pub dropped: u64,
+    pub last_recorded_at: DateTime<Utc>,
+}
+
+/// Disk encryption status of a host (dm-crypt as seen by the agent).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DiskEncryption {
+    /// Root filesystem on dm-crypt
+    Encrypted,
+    /// dm-crypt volumes, root filesystem not on one
+    Partial,
+    None,
+    Unknown,
+}
+
+impl DiskEncryption {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DiskEncryption::Encrypted => "encrypted",
+            DiskEncryption::Partial => "partial",
+            DiskEncryption::None => "none",
+            DiskEncryption::Unknown => "unknown",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "encrypted" => Some(DiskEncryption::Encrypted),
+            "partial" => Some(DiskEncryption::Partial),
+            "none" => Some(DiskEncryption::None),
+            "unknown" => Some(DiskEncryption::Unknown),
+            _ => None,
+        }
+    }
+}
+
+/// Security tooling installed on a host.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct SecurityTool {
+    pub name: String,
+    /// edr, antivirus, hids, fim, audit, runtime, mac
+    pub kind: String,
+}
+
+/// One host_inventory row: the latest facts an agent reported about its host.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct HostInventoryRecord {
+    pub agent_id: Uuid,
+    pub hostname: Option<String>,
+    pub os_name: Option<String>,
+    pub os_version: Option<String>,
+    pub kernel_release: String,
+    pub kernel_version: Option<String>,
+    pub arch: String,
+    pub cpu_model: Option<String>,
+    pub cpu_cores: u32,
+    pub memory_total_bytes: u64,
+    pub security_tools: Vec<SecurityTool>,
+    pub disk_encryption: DiskEncryption,
+    pub encrypted_volumes: Vec<String>,
+    /// Envelope event_id of the report (raw_events lineage)
+    pub source_event_id: String,
+    /// Agent-side time of the report
+    pub collected_at: DateTime<Utc>,
+    pub received_at: DateTime<Utc>,
+}
+
+/// Result of checking one event's signature - signature_validation_events row.
+#[derive(Debug, Clone)]
+pub struct SignatureValidationRecord {
+    /// Envelope event_id of the checked event (object_type raw_event)
+    pub object_id: Uuid,
+    pub signature_b64: String,
+    /// valid, invalid or unknown
+    pub status: String,
+    /// signer_id the event claimed
+    pub signer_identity: String,
+    /// Where the key came from and the signed sequence
+    pub context: JsonValue,
+    pub error_details: Option<String>,
+}
+
+/// Unsuppressed detections of one severity.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct SeverityCount {
+    pub severity: String,
+    pub detections: u64,
+}
+
+/// Raw events received in one minute.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct EventRate {
+    /// Start of the minute
+    pub minute: DateTime<Utc>,
+    pub events: u64,
+}
+
+/// Agents of one agent type.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct AgentTypeCount {
+    pub agent_type: String,
+    pub agents: u64,
+    /// Not decommissioned and seen within the reporting window
+    pub reporting: u64,
+}
+
+/// Agent counts across the fleet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct FleetSummary {
+    pub agents: u64,
+    pub decommissioned: u64,
+    pub reporting: u64,
+    pub by_type: Vec<AgentTypeCount>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StoredRawEvent {
+    pub raw_event_id: Uuid,
+    pub source: TelemetrySource,
+    pub agent_id: Uuid,
+    pub observed_at: DateTime<Utc>,
+    pub received_at: DateTime<Utc>,
+    pub event_name: String,
+    pub payload_json: JsonValue,
+    pub payload_storage: PayloadStorage,
+    pub residency_region: Option<String>,
+    pub site: Option<String>,
+    pub zone: Option<String>,
+}
+
+/// Open unit of work. Dropping without `commit` leaves the work uncommitted
+/// (SQLite rolls back on drop; Postgres callers must call `rollback`).
+#[async_trait]
+pub trait StorageTx: Send {
+    async fn insert_raw(&mut self, raw: &RawEventRecord) -> Result<Uuid, StorageError>;
+    async fn insert_telemetry(&mut self, telemetry: &TelemetryRecord) -> Result<(), StorageError>;
+    async fn append_audit(&mut self, audit: &AuditRecord) -> Result<Uuid, StorageError>;
+    /// Postgres applies active suppression rules first (suppression.rs): a suppressed detection is
+    /// stored without its detection.created webhook. An unsuppressed critical detection also
+    /// queues a packet capture of its host when enabled (pcap_capture.rs).
+    async fn insert_detection(&mut self, detection: &DetectionRecord) -> Result<Uuid, StorageError>;
+    async fn insert_identity_conflict(&mut self, conflict: &IdentityConflictRecord, detection_id: Uuid) -> Result<(), StorageError>;
+    /// Close an open conflict and mark its pending events; `None` if no such open conflict.
+    async fn resolve_identity_conflict(
+        &mut self,
+        conflict_id: Uuid,
+        resolution: ConflictResolution,
+        reason: &str,
+    ) -> Result<Option<u64>, StorageError>;
+    async fn insert_quarantined_event(&mut self, event: &QuarantinedEventRecord) -> Result<Uuid, StorageError>;
+    async fn enqueue_outbox(&mut self, entry: &OutboxRecord) -> Result<Uuid, StorageError>;
+    /// Record a signature check made by `validator_component_id`.
+    async fn insert_signature_validation(
+        &mut self,
+        validator_component_id: Uuid,
+        record: &SignatureValidationRecord,
+    ) -> Result<Uuid, StorageError>;
+    /// Add to the daily drop totals (rows are created on first use).
+    async fn add_drops(&mut self, drops: &[DropCount]) -> Result<(), StorageError>;
+    /// Move the cumulative counter an agent run reported for `reason` to `cumulative`; returns
+    /// how far it moved (0 for a stale or repeated report).
+    async fn advance_drop_counter(
+        &mut self,
+        agent_id: Uuid,
+        run_id: &str,
+        reason: &str,
+        cumulative: u64,
+    ) -> Result<u64, StorageError>;
+    /// Replace the agent's host_inventory row unless the stored report is newer; returns
+    /// whether the row was written.
+    async fn upsert_host_inventory(&mut self, inventory: &HostInventoryRecord) -> Result<bool, StorageError>;
+    /// Acknowledge this transaction's commit before it is flushed to disk (load shedding). A crash
+    /// may then lose the transaction, never corrupt it. Backends without the distinction ignore it.
+    async fn relax_durability(&mut self) -> Result<(), StorageError> {
+        Ok(())
+    }
+    async fn commit(self: Box<Self>) -> Result<(), StorageError>;
+    async fn rollback(self: Box<Self>);
+}
+
+#[async_trait]
+pub trait TelemetryStore: Send + Sync {
+    fn backend(&self) -> StorageBackend;
+
+    /// Resolve (or auto-register) the agent row for a producer identity.
+    async fn resolve_agent(&self, component_identity: &str, source: TelemetrySource) -> Result<Uuid, StorageError>;
+
+    /// Component row used as actor on ingestion audit entries.
+    async fn ingestion_component(&self) -> Result<Uuid, StorageError>;
+
+    async fn begin(&self) -> Result<Box<dyn StorageTx>, StorageError>;
+
+    /// Raw events newest first, bounded by `query.limit`.
+    async fn query(&self, query: &TelemetryQuery) -> Result<Vec<StoredRawEvent>, StorageError>;
+
+    /// Identity conflicts still open (quarantine state restored at startup).
+    async fn open_identity_conflicts(&self) -> Result<Vec<IdentityConflictRecord>, StorageError>;
+
+    /// Claim up to `limit` due outbox messages, oldest first, leasing them for `lease`.
+    /// A message whose lease expires without `complete_outbox` is claimed again.
+    async fn claim_outbox(&self, limit: i64, lease: std::time::Duration) -> Result<Vec<OutboxEntry>, StorageError>;
+
+    async fn complete_outbox(&self, outbox_id: Uuid) -> Result<(), StorageError>;
+
+    /// Record a failed publish; the message is due again after `retry_in`.
+    async fn retry_outbox(&self, outbox_id: Uuid, error: &str, retry_in: std::time::Duration) -> Result<(), StorageError>;
+
+    /// Delete messages published before `published_before`; returns the number removed.
+    async fn purge_outbox(&self, published_before: DateTime<Utc>) -> Result<u64, StorageError>;
+
+    /// Daily drop totals from `since` on, newest day first, bounded by `limit`.
+    async fn drop_summary(&self, since: NaiveDate, limit: i64) -> Result<Vec<DropSummary>, StorageError>;
+
+    /// Delete agent drop counters not advanced since `before` (runs that ended); returns the number removed.
+    async fn purge_drop_counters(&self, before: DateTime<Utc>) -> Result<u64, StorageError>;
+
+    /// Latest inventory per agent, ordered by agent, bounded by `limit`.
+    async fn list_host_inventory(&self, limit: i64) -> Result<Vec<HostInventoryRecord>, StorageError>;
+
+    async fn host_inventory(&self, agent_id: Uuid) -> Result<Option<HostInventoryRecord>, StorageError>;
+
+    /// Unsuppressed detections created since `since`, per severity, most severe first.
+    async fn detection_severity_counts(&self, since: DateTime<Utc>) -> Result<Vec<SeverityCount>, StorageError>;
+
+    /// Raw events received since `since`, per minute, oldest first; minutes without events are omitted.
+    async fn event_rate(&self, since: DateTime<Utc>) -> Result<Vec<EventRate>, StorageError>;
+
+    /// Agents per type; `reporting` counts those seen since `seen_since`.
+    async fn fleet_summary(&self, seen_since: DateTime<Utc>) -> Result<FleetSummary, StorageError>;
+}
+
+/// Open the configured backend. The Postgres client is also returned because the
+/// control plane (agent tokens) stays Postgres-only.
+pub async fn open_from_env(
+    config: &StorageConfig,
+) -> Result<(Arc<dyn TelemetryStore>, Option<Arc<tokio_postgres::Client>>), StorageError> {
+    match config.backend {
+        StorageBackend::Postgres => {
+            let client = postgres::connect_from_env().await?;
+            crate::schema_compat::enforce(&client, "ingest", crate::schema_compat::INGEST_WRITTEN_TABLES)
+                .await
+                .map_err(|e| StorageError::Config(format!("Schema compatibility: {e}")))?;
+            let store = PostgresStore::new(client.clone()).with_pcap_capture(config.pcap_capture.clone());
+            Ok((Arc::new(store), Some(client)))
+        }
+        StorageBackend::Sqlite => Ok((Arc::new(SqliteStore::open(&config.sqlite_path)?), None)),
+    }
+}
+
+/// Append a single audit entry in its own unit of work.
+pub async fn append_audit_standalone(store: &dyn TelemetryStore, audit: &AuditRecord) -> Result<Uuid, StorageError> {
+    let mut tx = store.begin().await?;
+    match tx.append_audit(audit).await {
+        Ok(id) => {
+            tx.commit().await?;
+            Ok(id)
+        }
+        Err(e) => {
+            tx.rollback().await;
+            Err(e)
+        }
+    }
+}
+
+pub(crate) fn validate_limit(limit: i64) -> Result<i64, StorageError> {
+    if !(1..=10_000).contains(&limit) {
+        return Err(StorageError::InvalidInput(format!("limit must be in 1..=10000 (got {})", limit)));
+    }
+    Ok(limit)
+}
@@ -0,0 +1,1127 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/storage/postgres.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: PostgreSQL telemetry store (default backend) - raw_events, typed telemetry tables and the hash-chained immutable_audit_log against the authoritative schema
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde_json::Value as JsonValue;
+use crypto::digest::Sha256;
+use tokio_postgres::types::Json;
+use tokio_postgres::{Client, NoTls};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::attack;
+use crate::maintenance::{self, MaintenanceAction};
+use crate::pcap_capture::{self, PcapCaptureConfig};
+use crate::suppression;
+use crate::webhooks;
+
+use super::{
+    validate_limit, AgentTypeCount, AuditRecord, ConflictResolution, DetectionRecord, DiskEncryption, DropCount, DropOrigin, DropSummary, EventRate,
+    FleetSummary, HostInventoryRecord, IdentityConflictRecord, IdentityOrigin, OutboxEntry, OutboxRecord, PayloadStorage, QuarantinedEventRecord, RawEventRecord,
+    SeverityCount, SignatureValidationRecord, StorageBackend, StorageError, StorageTx, StoredRawEvent, TelemetryQuery, TelemetryRecord, TelemetrySource, TelemetryStore,
+};
+
+const HOST_INVENTORY_COLUMNS: &str = "agent_id, hostname, os_name, os_version, kernel_release, kernel_version, arch, cpu_model, \
+     cpu_cores, memory_total_bytes, security_tools, disk_encryption, encrypted_volumes, source_event_id, collected_at, received_at";
+
+fn host_inventory_row(row: &tokio_postgres::Row) -> Result<HostInventoryRecord, StorageError> {
+    let disk_encryption: String = row.get(11);
+    Ok(HostInventoryRecord {
+        agent_id: row.get(0),
+        hostname: row.get(1),
+        os_name: row.get(2),
+        os_version: row.get(3),
+        kernel_release: row.get(4),
+        kernel_version: row.get(5),
+        arch: row.get(6),
+        cpu_model: row.get(7),
+        cpu_cores: row.get::<_, i32>(8).max(0) as u32,
+        memory_total_bytes: row.get::<_, i64>(9).max(0) as u64,
+        security_tools: row.get::<_, Json<_>>(10).0,
+        disk_encryption: DiskEncryption::parse(&disk_encryption)
+            .ok_or_else(|| StorageError::Query(format!("stored disk_encryption '{}' invalid", disk_encryption)))?,
+        encrypted_volumes: row.get::<_, Json<_>>(12).0,
+        source_event_id: row.get(13),
+        collected_at: row.get(14),
+        received_at: row.get(15),
+    })
+}
+
+/// Connect using DB_HOST / DB_PORT / DB_NAME / DB_USER / DB_PASS and set the ransomeye search_path.
+pub async fn connect_from_env() -> Result<Arc<Client>, StorageError> {
+    let db_host = std::env::var("DB_HOST")
+        .unwrap_or_else(|_| "localhost".to_string());
+    let db_port = std::env::var("DB_PORT")
+        .unwrap_or_else(|_| "5432".to_string())
+        .parse::<u16>()
+        .map_err(|e| StorageError::Config(format!("Invalid DB_PORT: {}", e)))?;
+    let db_name = std::env::var("DB_NAME")
+        .unwrap_or_else(|_| "ransomeye".to_string());
+    let db_user = std::env::var("DB_USER")
+        .unwrap_or_else(|_| "gagan".to_string());
+    let db_pass = std::env::var("DB_PASS")
+        .unwrap_or_else(|_| "gagan".to_string());
+
+    let connection_string = format!(
+        "host={} port={} dbname={} user={} password={}",
+        db_host, db_port, db_name, db_user, db_pass
+    );
+
+    let (client, connection) = tokio_postgres::connect(&connection_string, NoTls)
+        .await
+        .map_err(|e| StorageError::Connection(format!("Database connection failed: {}", e)))?;
+
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            error!("Database connection error: {}", e);
+        }
+    });
+
+    // Set search_path
+    client
+        .batch_execute("SET search_path = ransomeye, public;")
+        .await
+        .map_err(|e| StorageError::Connection(format!("Failed to set search_path: {}", e)))?;
+
+    info!("Postgres telemetry store connected ({}:{}/{})", db_host, db_port, db_name);
+    Ok(Arc::new(client))
+}
+
+fn query_err(context: &str, e: impl std::fmt::Display) -> StorageError {
+    StorageError::Query(format!("{}: {}", context, e))
+}
+
+/// One store per connection: its transactions run one at a time.
+pub struct PostgresStore {
+    db: Arc<Client>,
+    /// Held from BEGIN until COMMIT/ROLLBACK. Statements of concurrent callers would otherwise
+    /// interleave inside one session's transaction (and share its audit chain advisory lock).
+    tx_lock: Arc<tokio::sync::Mutex<()>>,
+    pcap_capture: Option<Arc<PcapCaptureConfig>>,
+}
+
+impl PostgresStore {
+    pub fn new(db: Arc<Client>) -> Self {
+        Self { db, tx_lock: Arc::new(tokio::sync::Mutex::new(())), pcap_capture: None }
+    }
+
+    /// Queue packet captures for critical detections (pcap_capture.rs); None leaves them off.
+    pub fn with_pcap_capture(mut self, config: Option<PcapCaptureConfig>) -> Self {
+        self.pcap_capture = config.map(Arc::new);
+        self
+    }
+}
+
+#[async_trait]
+impl TelemetryStore for PostgresStore {
+    fn backend(&self) -> StorageBackend {
+        StorageBackend::Postgres
+    }
+
+    async fn resolve_agent(&self, component_identity: &str, source: TelemetrySource) -> Result<Uuid, StorageError> {
+        get_or_create_agent(&self.db, component_identity, source.as_str())
+            .await
+            .map_err(|e| query_err("get_or_create_agent", e))
+    }
+
+    async fn ingestion_component(&self) -> Result<Uuid, StorageError> {
+        get_or_create_ingestion_component(&self.db)
+            .await
+            .map_err(|e| query_err("get_or_create_ingestion_component", e))
+    }
+
+    async fn begin(&self) -> Result<Box<dyn StorageTx>, StorageError> {
+        let tx_guard = self.tx_lock.clone().lock_owned().await;
+        // Use explicit SQL BEGIN since we have Arc<Client> (can't use transaction API)
+        self.db.execute("BEGIN", &[]).await.map_err(|e| query_err("Failed to start transaction", e))?;
+        Ok(Box::new(PostgresTx { db: self.db.clone(), pcap_capture: self.pcap_capture.clone(), _tx_guard: tx_guard }))
+    }
+
+    async fn query(&self, query: &TelemetryQuery) -> Result<Vec<StoredRawEvent>, StorageError> {
+        let limit = validate_limit(query.limit)?;
+        let source = query.source.map(|s| s.as_str());
+        let rows = self.db.query(
+            r#"
+            SELECT raw_event_id, source_type::text, source_agent_id, observed_at, received_at,
+                   event_name, payload_json, payload_storage, residency_region, site, zone
+            FROM raw_events
+            WHERE ($1::text IS NULL OR source_type::text = $1)
+              AND ($2::uuid IS NULL OR source_agent_id = $2)
+              AND ($3::timestamptz IS NULL OR observed_at >= $3)
+              AND ($4::timestamptz IS NULL OR observed_at < $4)
+            ORDER BY observed_at DESC
+            LIMIT $5
+            "#,
+            &[&source, &query.agent_id, &query.observed_from, &query.observed_to, &limit],
+        ).await.map_err(|e| query_err("raw_events query", e))?;
+
+        let mut events = Vec::with_capacity(rows.len());
+        for row in rows {
+            let source_type: String = row.get(1);
+            // Sources other than the two telemetry producers are not exposed through this store
+            let Some(source) = TelemetrySource::parse(&source_type) else { continue };
+            let payload_storage: String = row.get(7);
+            let payload_storage = PayloadStorage::parse(&payload_storage)
+                .ok_or_else(|| StorageError::Query(format!("stored payload_storage '{}' invalid", payload_storage)))?;
+            events.push(StoredRawEvent {
+                raw_event_id: row.get(0),
+                source,
+                agent_id: row.get(2),
+                observed_at: row.get(3),
+                received_at: row.get(4),
+                event_name: row.get(5),
+                payload_json: row.get(6),
+                payload_storage,
+                residency_region: row.get(8),
+                site: row.get(9),
+                zone: row.get(10),
+            });
+        }
+        Ok(events)
+    }
+
+    async fn open_identity_conflicts(&self) -> Result<Vec<IdentityConflictRecord>, StorageError> {
+        let rows = self.db.query(
+            r#"
+            SELECT conflict_id, component_type, component_identity, agent_id, first_origin, conflicting_origin, opened_at
+            FROM identity_conflicts
+            WHERE status = 'open'
+            ORDER BY opened_at
+            "#,
+            &[],
+        ).await.map_err(|e| query_err("identity_conflicts query", e))?;
+        Ok(rows
+            .iter()
+            .map(|row| IdentityConflictRecord {
+                conflict_id: row.get(0),
+                component_type: row.get(1),
+                component_identity: row.get(2),
+                agent_id: row.get(3),
+                first_origin: row.get::<_, Json<IdentityOrigin>>(4).0,
+                conflicting_origin: row.get::<_, Json<IdentityOrigin>>(5).0,
+                opened_at: row.get(6),
+            })
+            .collect())
+    }
+
+    async fn claim_outbox(&self, limit: i64, lease: std::time::Duration) -> Result<Vec<OutboxEntry>, StorageError> {
+        let rows = self.db.query(
+            r#"
+            UPDATE ingest_outbox
+            SET next_attempt_at = now() + make_interval(secs => $2)
+            WHERE outbox_id IN (
+                SELECT outbox_id FROM ingest_outbox
+                WHERE status = 'pending' AND next_attempt_at <= now()
+                ORDER BY created_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING outbox_id, topic, aggregate_id, payload, attempts, created_at
+            "#,
+            &[&limit, &lease.as_secs_f64()],
+        ).await.map_err(|e| query_err("ingest_outbox claim", e))?;
+        let mut entries: Vec<OutboxEntry> = rows
+            .iter()
+            .map(|row| OutboxEntry {
+                outbox_id: row.get(0),
+                topic: row.get(1),
+                aggregate_id: row.get(2),
+                payload: row.get(3),
+                attempts: row.get(4),
+                created_at: row.get(5),
+            })
+            .collect();
+        // RETURNING order is unspecified
+        entries.sort_by_key(|e| e.created_at);
+        Ok(entries)
+    }
+
+    async fn complete_outbox(&self, outbox_id: Uuid) -> Result<(), StorageError> {
+        self.db.execute(
+            "UPDATE ingest_outbox SET status = 'published', published_at = now(), last_error = NULL WHERE outbox_id = $1",
+            &[&outbox_id],
+        ).await.map_err(|e| query_err("ingest_outbox complete", e))?;
+        Ok(())
+    }
+
+    async fn retry_outbox(&self, outbox_id: Uuid, error: &str, retry_in: std::time::Duration) -> Result<(), StorageError> {
+        self.db.execute(
+            r#"
+            UPDATE ingest_outbox
+            SET attempts = attempts + 1, last_error = $2, next_attempt_at = now() + make_interval(secs => $3)
+            WHERE outbox_id = $1 AND status = 'pending'
+            "#,
+            &[&outbox_id, &error, &retry_in.as_secs_f64()],
+        ).await.map_err(|e| query_err("ingest_outbox retry", e))?;
+        Ok(())
+    }
+
+    async fn purge_outbox(&self, published_before: DateTime<Utc>) -> Result<u64, StorageError> {
+        self.db.execute(
+            "DELETE FROM ingest_outbox WHERE status = 'published' AND published_at < $1",
+            &[&published_before],
+        ).await.map_err(|e| query_err("ingest_outbox purge", e))
+    }
+
+    async fn drop_summary(&self, since: NaiveDate, limit: i64) -> Result<Vec<DropSummary>, StorageError> {
+        let limit = validate_limit(limit)?;
+        let rows = self.db.query(
+            r#"
+            SELECT agent_id, drop_day, origin, reason, dropped, last_recorded_at
+            FROM telemetry_drops_daily
+            WHERE drop_day >= $1
+            ORDER BY drop_day DESC, agent_id, origin, reason
+            LIMIT $2
+            "#,
+            &[&since, &limit],
+        ).await.map_err(|e| query_err("telemetry_drops_daily query", e))?;
+        rows.iter()
+            .map(|row| {
+                let origin: String = row.get(2);
+                Ok(DropSummary {
+                    agent_id: row.get(0),
+                    day: row.get(1),
+                    origin: DropOrigin::parse(&origin)
+                        .ok_or_else(|| StorageError::Query(format!("stored drop origin '{}' invalid", origin)))?,
+                    reason: row.get(3),
+                    dropped: row.get::<_, i64>(4).max(0) as u64,
+                    last_recorded_at: row.get(5),
+                })
+            })
+            .collect()
+    }
+
+    async fn purge_drop_counters(&self, before: DateTime<Utc>) -> Result<u64, StorageError> {
+        self.db.execute(
+            "DELETE FROM agent_drop_counters WHERE updated_at < $1",
+            &[&before],
+        ).await.map_err(|e| query_err("agent_drop_counters purge", e))
+    }
+
+    async fn list_host_inventory(&self, limit: i64) -> Result<Vec<HostInventoryRecord>, StorageError> {
+        let limit = validate_limit(limit)?;
+        let rows = self.db.query(
+            &format!("SELECT {} FROM host_inventory ORDER BY agent_id LIMIT $1", HOST_INVENTORY_COLUMNS),
+            &[&limit],
+        ).await.map_err(|e| query_err("host_inventory query", e))?;
+        rows.iter().map(host_inventory_row).collect()
+    }
+
+    async fn host_inventory(&self, agent_id: Uuid) -> Result<Option<HostInventoryRecord>, StorageError> {
+        self.db.query_opt(
+            &format!("SELECT {} FROM host_inventory WHERE agent_id = $1", HOST_INVENTORY_COLUMNS),
+            &[&agent_id],
+        ).await.map_err(|e| query_err("host_inventory lookup", e))?
+            .as_ref()
+            .map(host_inventory_row)
+            .transpose()
+    }
+
+    async fn detection_severity_counts(&self, since: DateTime<Utc>) -> Result<Vec<SeverityCount>, StorageError> {
+        // severity_level sorts from debug to critical
+        let rows = self.db.query(
+            r#"
+            SELECT severity::text, count(*)
+            FROM detection_results
+            WHERE created_at >= $1 AND suppression_id IS NULL
+            GROUP BY severity
+            ORDER BY severity DESC
+            "#,
+            &[&since],
+        ).await.map_err(|e| query_err("detection_results severity counts", e))?;
+        Ok(rows
+            .iter()
+            .map(|row| SeverityCount { severity: row.get(0), detections: row.get::<_, i64>(1).max(0) as u64 })
+            .collect())
+    }
+
+    async fn event_rate(&self, since: DateTime<Utc>) -> Result<Vec<EventRate>, StorageError> {
+        let rows = self.db.query(
+            r#"
+            SELECT date_trunc('minute', received_at), count(*)
+            FROM raw_events
+            WHERE received_at >= $1
+            GROUP BY 1
+            ORDER BY 1
+            "#,
+            &[&since],
+        ).await.map_err(|e| query_err("raw_events rate", e))?;
+        Ok(rows
+            .iter()
+            .map(|row| EventRate { minute: row.get(0), events: row.get::<_, i64>(1).max(0) as u64 })
+            .collect())
+    }
+
+    async fn fleet_summary(&self, seen_since: DateTime<Utc>) -> Result<FleetSummary, StorageError> {
+        let rows = self.db.query(
+            r#"
+            SELECT agent_type::text,
+                   count(*),
+                   count(*) FILTER (WHERE decommissioned_at IS NOT NULL OR NOT is_active),
+                   count(*) FILTER (WHERE decommissioned_at IS NULL AND is_active AND last_seen_at >= $1)
+            FROM agents
+            GROUP BY agent_type
+            ORDER BY agent_type::text
+            "#,
+            &[&seen_since],
+        ).await.map_err(|e| query_err("agents fleet summary", e))?;
+        let mut summary = FleetSummary { agents: 0, decommissioned: 0, reporting: 0, by_type: Vec::with_capacity(rows.len()) };
+        for row in &rows {
+            let count = |i: usize| row.get::<_, i64>(i).max(0) as u64;
+            summary.agents += count(1);
+            summary.decommissioned += count(2);
+            summary.reporting += count(3);
+            summary.by_type.push(AgentTypeCount { agent_type: row.get(0), agents: count(1), reporting: count(3) });
+        }
+        Ok(summary)
+    }
+}
+
+struct PostgresTx {
+    db: Arc<Client>,
+    pcap_capture: Option<Arc<PcapCaptureConfig>>,
+    /// Released when commit/rollback consume the transaction
+    _tx_guard: tokio::sync::OwnedMutexGuard<()>,
+}
+
+#[async_trait]
+impl StorageTx for PostgresTx {
+    async fn insert_raw(&mut self, raw: &RawEventRecord) -> Result<Uuid, StorageError> {
+        // Source label is a closed enum, so casting a bound text parameter is safe
+        let row = self.db.query_one(
+            r#"
+            INSERT INTO raw_events (
+                source_type, source_agent_id, observed_at, received_at,
+                event_name, payload_json, payload_sha256, payload_storage, payload_storage_reason,
+                residency_region, site, zone
+            )
+            VALUES ($1::text::event_source_type, $2, $3, NOW(), $4, $5, $6, $7, $8, $9, $10, $11)
+            RETURNING raw_event_id
+            "#,
+            &[
+                &raw.source.as_str(),
+                &raw.agent_id,
+                &raw.observed_at,
+                &raw.event_name,
+                &Json(&*raw.payload_json),
+                &raw.payload_sha256,
+                &raw.payload_storage.as_str(),
+                &raw.payload_storage_reason,
+                &raw.residency_region,
+                &raw.zone.as_ref().map(|z| z.site.as_str()),
+                &raw.zone.as_ref().map(|z| z.zone.as_str()),
+            ],
+        ).await.map_err(|e| query_err("raw_events insert", e))?;
+        Ok(row.get(0))
+    }
+
+    async fn insert_telemetry(&mut self, telemetry: &TelemetryRecord) -> Result<(), StorageError> {
+        match telemetry {
+            TelemetryRecord::Linux(t) => {
+                let p = &t.provenance;
+                // INSERT #1 — REQUIRED FIELDS ONLY (within transaction)
+                self.db.execute(
+                    r#"
+                    INSERT INTO linux_agent_telemetry (
+                        agent_id, source_message_id, source_nonce, source_component_identity,
+                        source_host_id, source_signature_b64, source_signature_alg, source_data_hash_hex,
+                        observed_at, event_name, event_category, pid, uid, process_name
+                    )
+                    VALUES (
+                        $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14
+                    )
+                    "#,
+                    &[
+                        &p.agent_id,
+                        &p.message_id,
+                        &p.nonce,
+                        &p.component_identity,
+                        &t.host_id,
+                        &p.signature_b64,
+                        &p.signature_alg,
+                        &p.data_hash_hex,
+                        &p.observed_at,
+                        &t.event_name,
+                        &t.event_category,
+                        &t.pid,
+                        &t.uid,
+                        &t.process_name.as_deref(),
+                    ],
+                ).await.map_err(|e| {
+                    if let Some(db_err) = e.as_db_error() {
+                        error!("PostgreSQL Error: Code={:?}, Message={}", db_err.code(), db_err.message());
+                        if let Some(detail) = db_err.detail() {
+                            error!("Detail: {}", detail);
+                        }
+                    }
+                    query_err("linux_agent_telemetry insert (required fields)", e)
+                })?;
+
+                // UPDATE #2 — OPTIONAL FIELDS. The savepoint keeps a failed UPDATE from aborting
+                // the transaction, so raw_events + required telemetry fields still commit.
+                self.db.batch_execute("SAVEPOINT linux_optional_fields").await
+                    .map_err(|e| query_err("savepoint", e))?;
+                let update_result = self.db.execute(
+                    r#"
+                    UPDATE linux_agent_telemetry
+                    SET file_path = $1,
+                        network_src_ip = $2::inet,
+                        network_dst_ip = $3::inet,
+                        payload = $4::jsonb,
+                        payload_sha256 = $5,
+                        protocol = $6,
+                        cmdline = $7,
+                        container_id = $8,
+                        container_image = $9,
+                        pod_name = $10,
+                        pod_namespace = $11,
+                        network_src_port = $12,
+                        network_dst_port = $13,
+                        netns_inode = $14,
+                        host_netns = $15
+                    WHERE source_message_id = $16
+                    "#,
+                    &[
+                        &t.file_path.as_deref(),
+                        &t.network_src_ip.as_deref(),
+                        &t.network_dst_ip.as_deref(),
+                        &t.payload_json,
+                        &t.payload_sha256,
+                        &t.protocol.as_deref(),
+                        &t.cmdline.as_deref(),
+                        &t.container_id.as_deref(),
+                        &t.container_image.as_deref(),
+                        &t.pod_name.as_deref(),
+                        &t.pod_namespace.as_deref(),
+                        &t.network_src_port,
+                        &t.network_dst_port,
+                        &t.netns_inode,
+                        &t.host_netns,
+                        &p.message_id,
+                    ],
+                ).await;
+                match update_result {
+                    Ok(_) => self.db.batch_execute("RELEASE SAVEPOINT linux_optional_fields").await,
+                    Err(e) => {
+                        warn!("Failed to update linux_agent_telemetry optional fields (non-fatal): {}", e);
+                        self.db.batch_execute("ROLLBACK TO SAVEPOINT linux_optional_fields").await
+                    }
+                }
+                .map_err(|e| query_err("savepoint release", e))?;
+                Ok(())
+            }
+            TelemetryRecord::Dpi(t) => {
+                let p = &t.provenance;
+                self.db.execute(
+                    r#"
+                    INSERT INTO dpi_probe_telemetry (
+                        agent_id, source_message_id, source_nonce, source_component_identity,
+                        source_signature_b64, source_signature_alg, source_data_hash_hex,
+                        observed_at, src_ip, src_port, dst_ip, dst_port, protocol,
+                        bytes_in, bytes_out, packets_in, packets_out, tls_sni,
+                        http_host, http_method, http_path, iface_name, flow_id, payload, payload_sha256,
+                        packet_count, byte_count, ja3, ja3s, metadata
+                    )
+                    VALUES (
+                        $1, $2, $3, $4, $5, $6, $7, $8, $9::inet, $10, $11::inet, $12, $13, $14, $15, $16, $17,
+                        $18, $19, $20, $21, $22, $23, $24::jsonb, $25, $26, $27, $28, $29, $30::jsonb
+                    )
+                    "#,
+                    &[
+                        &p.agent_id,
+                        &p.message_id,
+                        &p.nonce,
+                        &p.component_identity,
+                        &p.signature_b64,
+                        &p.signature_alg,
+                        &p.data_hash_hex,
+                        &p.observed_at,
+                        &t.src_ip.as_deref(),
+                        &t.src_port,
+                        &t.dst_ip.as_deref(),
+                        &t.dst_port,
+                        &t.protocol.as_deref(),
+                        &t.bytes_in,
+                        &t.bytes_out,
+                        &t.packets_in,
+                        &t.packets_out,
+                        &t.tls_sni.as_deref(),
+                        &t.http_host.as_deref(),
+                        &t.http_method.as_deref(),
+                        &t.http_path.as_deref(),
+                        &t.iface_name.as_deref(),
+                        &t.flow_id.as_deref(),
+                        &t.payload_json,
+                        &t.payload_sha256,
+                        &t.packet_count,
+                        &t.byte_count,
+                        &t.ja3.as_deref(),
+                        &t.ja3s.as_deref(),
+                        &t.metadata_json.as_deref(),
+                    ],
+                ).await.map_err(|e| query_err("dpi_probe_telemetry insert", e))?;
+                Ok(())
+            }
+        }
+    }
+
+    async fn append_audit(&mut self, audit: &AuditRecord) -> Result<Uuid, StorageError> {
+        insert_immutable_audit_log(
+            &self.db,
+            audit.actor_component_id,
+            audit.actor_agent_id,
+            &audit.action,
+            &audit.object_type,
+            audit.object_id,
+            audit.event_time,
+            &audit.payload_json,
+            &audit.payload_sha256,
+        )
+        .await
+        .map_err(|e| query_err(&format!("immutable_audit_log insert ({})", audit.action), e))
+    }
+
+    async fn insert_detection(&mut self, detection: &DetectionRecord) -> Result<Uuid, StorageError> {
+        // Suppressed detections are stored and counted, but raise no alert
+        let suppression_id = suppression::apply(&self.db, detection)
+            .await
+            .map_err(|e| query_err("detection_suppressions match", e))?;
+        // Maintenance windows only act on what no suppression rule already silenced
+        let maintenance = match suppression_id {
+            Some(_) => None,
+            None => maintenance::apply(&self.db, detection)
+                .await
+                .map_err(|e| query_err("maintenance_windows match", e))?,
+        };
+        let downgraded;
+        let (detection, original_severity) = match maintenance.as_ref().and_then(|hit| hit.downgrade_to.clone()) {
+            Some(severity) => {
+                downgraded = DetectionRecord { severity, ..detection.clone() };
+                (&downgraded, Some(detection.severity.as_str()))
+            }
+            None => (detection, None),
+        };
+        let maintenance_window_id = maintenance.as_ref().map(|hit| hit.window_id);
+        let attack = attack::for_detector(&detection.detection_engine, &detection.detection_name);
+        let (mitre_tactic, mitre_technique) = match &attack {
+            Some(tag) => (Some(tag.tactic.as_str()), Some(tag.technique_id.as_str())),
+            None => (None, None),
+        };
+        // Severity label is a closed set chosen by ingest, so casting a bound text parameter is safe
+        let row = self.db.query_one(
+            r#"
+            INSERT INTO detection_results (
+                detection_engine, detection_name, detection_category, severity, confidence,
+                reasoning, artifacts, deterministic_key, suppression_id, mitre_tactic, mitre_technique,
+                maintenance_window_id, original_severity
+            )
+            VALUES ($1, $2, $3, $4::text::severity_level, $5, $6, $7, $8, $9, $10, $11, $12, $13::text::severity_level)
+            RETURNING detection_id
+            "#,
+            &[
+                &detection.detection_engine,
+                &detection.detection_name,
+                &detection.detection_category,
+                &detection.severity,
+                &detection.confidence,
+                &detection.reasoning,
+                &detection.artifacts,
+                &detection.deterministic_key,
+                &suppression_id,
+                &mitre_tactic,
+                &mitre_technique,
+                &maintenance_window_id,
+                &original_severity,
+            ],
+        ).await.map_err(|e| query_err("detection_results insert", e))?;
+        let detection_id: Uuid = row.get(0);
+        if let Some(suppression_id) = suppression_id {
+            info!(
+                "Detection suppressed | detection_id={} | detection_name={} | suppression_id={}",
+                detection_id, detection.detection_name, suppression_id
+            );
+            return Ok(detection_id);
+        }
+        match &maintenance {
+            Some(hit) if hit.action == MaintenanceAction::Suppress => {
+                info!(
+                    "Detection suppressed by maintenance window | detection_id={} | detection_name={} | window_id={}",
+                    detection_id, detection.detection_name, hit.window_id
+                );
+                return Ok(detection_id);
+            }
+            Some(hit) => info!(
+                "Detection downgraded by maintenance window | detection_id={} | detection_name={} | window_id={} | {} -> {}",
+                detection_id,
+                detection.detection_name,
+                hit.window_id,
+                original_severity.unwrap_or_default(),
+                detection.severity
+            ),
+            None => {}
+        }
+
+        // Same transaction: the webhook is queued only if the detection commits
+        let event = webhooks::WebhookEvent::new(
+            webhooks::DETECTION_CREATED,
+            serde_json::json!({
+                "detection_id": detection_id.to_string(),
+                "detection_engine": detection.detection_engine,
+                "detection_name": detection.detection_name,
+                "detection_category": detection.detection_category,
+                "severity": detection.severity,
+                "confidence": detection.confidence,
+                "reasoning": detection.reasoning,
+                "artifacts": detection.artifacts,
+                "mitre_tactic": mitre_tactic,
+                "mitre_technique": mitre_technique,
+                "maintenance_window_id": maintenance_window_id.map(|id| id.to_string()),
+                "original_severity": original_severity,
+            }),
+        );
+        webhooks::enqueue(&self.db, &event)
+            .await
+            .map_err(|e| query_err("webhook_deliveries enqueue", e))?;
+
+        // Same transaction: a critical detection commits together with the capture of its host
+        if let Some(config) = &self.pcap_capture {
+            pcap_capture::queue(&self.db, config, detection_id, detection)
+                .await
+                .map_err(|e| query_err("pcap_captures queue", e))?;
+        }
+        Ok(detection_id)
+    }
+
+    async fn insert_identity_conflict(&mut self, conflict: &IdentityConflictRecord, detection_id: Uuid) -> Result<(), StorageError> {
+        self.db.execute(
+            r#"
+            INSERT INTO identity_conflicts (
+                conflict_id, component_type, component_identity, agent_id, first_origin, conflicting_origin,
+                detection_id, opened_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+            &[
+                &conflict.conflict_id,
+                &conflict.component_type,
+                &conflict.component_identity,
+                &conflict.agent_id,
+                &Json(&conflict.first_origin),
+                &Json(&conflict.conflicting_origin),
+                &detection_id,
+                &conflict.opened_at,
+            ],
+        ).await.map_err(|e| query_err("identity_conflicts insert", e))?;
+        Ok(())
+    }
+
+    async fn resolve_identity_conflict(
+        &mut self,
+        conflict_id: Uuid,
+        resolution: ConflictResolution,
+        reason: &str,
+    ) -> Result<Option<u64>, StorageError> {
+        let closed = self.db.execute(
+            r#"
+            UPDATE identity_conflicts
+            SET status = 'resolved', resolution = $2, resolution_reason = $3, resolved_at = NOW()
+            WHERE conflict_id = $1 AND status = 'open'
+            "#,
+            &[&conflict_id, &resolution.as_str(), &reason],
+        ).await.map_err(|e| query_err("identity_conflicts resolve", e))?;
+        if closed == 0 {
+            return Ok(None);
+        }
+        let event_status = match resolution {
+            ConflictResolution::Release => "released",
+            ConflictResolution::Discard => "discarded",
+        };
+        let events = self.db.execute(
+            "UPDATE quarantined_events SET status = $2 WHERE conflict_id = $1 AND status = 'pending'",
+            &[&conflict_id, &event_status],
+        ).await.map_err(|e| query_err("quarantined_events resolve", e))?;
+        Ok(Some(events))
+    }
+
+    async fn insert_quarantined_event(&mut self, event: &QuarantinedEventRecord) -> Result<Uuid, StorageError> {
+        let row = self.db.query_one(
+            r#"
+            INSERT INTO quarantined_events (
+                quarantine_id, conflict_id, component_type, component_identity, message_id, source_ip, host_id,
+                signed_event, signed_event_sha256
+            )
+            VALUES (gen_random_uuid(), $1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING quarantine_id
+            "#,
+            &[
+                &event.conflict_id,
+                &event.component_type,
+                &event.component_identity,
+                &event.message_id,
+                &event.origin.source_ip,
+                &event.origin.host_id,
+                &Json(&*event.signed_event),
+                &event.signed_event_sha256,
+            ],
+        ).await.map_err(|e| query_err("quarantined_events insert", e))?;
+        Ok(row.get(0))
+    }
+
+    async fn enqueue_outbox(&mut self, entry: &OutboxRecord) -> Result<Uuid, StorageError> {
+        let row = self.db.query_one(
+            r#"
+            INSERT INTO ingest_outbox (outbox_id, topic, aggregate_id, payload)
+            VALUES (gen_random_uuid(), $1, $2, $3)
+            RETURNING outbox_id
+            "#,
+            &[&entry.topic, &entry.aggregate_id, &entry.payload],
+        ).await.map_err(|e| query_err("ingest_outbox insert", e))?;
+        Ok(row.get(0))
+    }
+
+    async fn insert_signature_validation(
+        &mut self,
+        validator_component_id: Uuid,
+        record: &SignatureValidationRecord,
+    ) -> Result<Uuid, StorageError> {
+        let row = self.db.query_one(
+            r#"
+            INSERT INTO signature_validation_events (
+                validator_component_id, object_type, object_id, signature_alg, signature_b64,
+                signature_status, signer_identity, cert_chain_json, error_details
+            )
+            VALUES ($1, 'raw_event'::trust_object_type, $2, 'Ed25519', $3, $4::text::signature_status, $5, $6, $7)
+            RETURNING signature_event_id
+            "#,
+            &[
+                &validator_component_id,
+                &record.object_id,
+                &record.signature_b64,
+                &record.status,
+                &record.signer_identity,
+                &record.context,
+                &record.error_details,
+            ],
+        ).await.map_err(|e| query_err("signature_validation_events insert", e))?;
+        Ok(row.get(0))
+    }
+
+    async fn add_drops(&mut self, drops: &[DropCount]) -> Result<(), StorageError> {
+        for drop in drops.iter().filter(|d| d.dropped > 0) {
+            self.db.execute(
+                r#"
+                INSERT INTO telemetry_drops_daily (agent_id, drop_day, origin, reason, dropped)
+                VALUES ($1, $2, $3, $4, $5)
+                ON CONFLICT (agent_id, drop_day, origin, reason)
+                DO UPDATE SET dropped = telemetry_drops_daily.dropped + EXCLUDED.dropped, last_recorded_at = now()
+                "#,
+                &[&drop.agent_id, &drop.day, &drop.origin.as_str(), &drop.reason, &(drop.dropped.min(i64::MAX as u64) as i64)],
+            ).await.map_err(|e| query_err("telemetry_drops_daily upsert", e))?;
+        }
+        Ok(())
+    }
+
+    async fn advance_drop_counter(
+        &mut self,
+        agent_id: Uuid,
+        run_id: &str,
+        reason: &str,
+        cumulative: u64,
+    ) -> Result<u64, StorageError> {
+        let cumulative = cumulative.min(i64::MAX as u64) as i64;
+        // Row lock: concurrent reports of the same run must not both count the same delta
+        let previous: Option<i64> = self.db.query_opt(
+            "SELECT cumulative FROM agent_drop_counters WHERE agent_id = $1 AND run_id = $2 AND reason = $3 FOR UPDATE",
+            &[&agent_id, &run_id, &reason],
+        ).await.map_err(|e| query_err("agent_drop_counters lookup", e))?
+            .map(|row| row.get(0));
+        self.db.execute(
+            r#"
+            INSERT INTO agent_drop_counters (agent_id, run_id, reason, cumulative)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (agent_id, run_id, reason)
+            DO UPDATE SET cumulative = GREATEST(agent_drop_counters.cumulative, EXCLUDED.cumulative), updated_at = now()
+            "#,
+            &[&agent_id, &run_id, &reason, &cumulative],
+        ).await.map_err(|e| query_err("agent_drop_counters upsert", e))?;
+        Ok((cumulative - previous.unwrap_or(0)).max(0) as u64)
+    }
+
+    async fn upsert_host_inventory(&mut self, inventory: &HostInventoryRecord) -> Result<bool, StorageError> {
+        let written = self.db.execute(
+            r#"
+            INSERT INTO host_inventory (agent_id, hostname, os_name, os_version, kernel_release, kernel_version, arch, cpu_model,
+                                        cpu_cores, memory_total_bytes, security_tools, disk_encryption, encrypted_volumes,
+                                        source_event_id, collected_at, received_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
+            ON CONFLICT (agent_id) DO UPDATE SET
+                hostname = EXCLUDED.hostname, os_name = EXCLUDED.os_name, os_version = EXCLUDED.os_version,
+                kernel_release = EXCLUDED.kernel_release, kernel_version = EXCLUDED.kernel_version, arch = EXCLUDED.arch,
+                cpu_model = EXCLUDED.cpu_model, cpu_cores = EXCLUDED.cpu_cores, memory_total_bytes = EXCLUDED.memory_total_bytes,
+                security_tools = EXCLUDED.security_tools, disk_encryption = EXCLUDED.disk_encryption,
+                encrypted_volumes = EXCLUDED.encrypted_volumes, source_event_id = EXCLUDED.source_event_id,
+                collected_at = EXCLUDED.collected_at, received_at = EXCLUDED.received_at
+            WHERE host_inventory.collected_at <= EXCLUDED.collected_at
+            "#,
+            &[
+                &inventory.agent_id,
+                &inventory.hostname,
+                &inventory.os_name,
+                &inventory.os_version,
+                &inventory.kernel_release,
+                &inventory.kernel_version,
+                &inventory.arch,
+                &inventory.cpu_model,
+                &(inventory.cpu_cores.min(i32::MAX as u32) as i32),
+                &(inventory.memory_total_bytes.min(i64::MAX as u64) as i64),
+                &Json(&inventory.security_tools),
+                &inventory.disk_encryption.as_str(),
+                &Json(&inventory.encrypted_volumes),
+                &inventory.source_event_id,
+                &inventory.collected_at,
+                &inventory.received_at,
+            ],
+        ).await.map_err(|e| query_err("host_inventory upsert", e))?;
+        Ok(written > 0)
+    }
+
+    async fn relax_durability(&mut self) -> Result<(), StorageError> {
+        // COMMIT returns once the WAL record is written, without waiting for the flush
+        self.db
+            .execute("SET LOCAL synchronous_commit = off", &[])
+            .await
+            .map_err(|e| query_err("Failed to relax transaction durability", e))?;
+        Ok(())
+    }
+
+    async fn commit(self: Box<Self>) -> Result<(), StorageError> {
+        self.db.execute("COMMIT", &[]).await.map_err(|e| query_err("Failed to commit transaction", e))?;
+        Ok(())
+    }
+
+    async fn rollback(self: Box<Self>) {
+        if let Err(e) = self.db.execute("ROLLBACK", &[]).await {
+            error!("Failed to roll back transaction: {}", e);
+        }
+    }
+}
+
+/// Labels of the Postgres `event_source_type` enum accepted for `agents.agent_type`.
+pub const AGENT_TYPES: &[&str] = &[
+    "linux_agent", "windows_agent", "dpi_probe", "core_engine", "ai_core", "alert_engine", "policy_engine",
+    "correlation_engine", "llm", "response_engine", "forensic_engine", "unknown",
+];
+
+/// Agent lookup by producer identity. Both values are bound parameters; the agent type is cast
+/// to the enum server-side, so no caller-supplied text ever becomes part of the statement.
+pub const AGENT_LOOKUP_SQL: &str = r#"
+    SELECT agent_id FROM agents
+    WHERE host_hostname = $1 AND agent_type = $2::text::event_source_type
+    LIMIT 1
+"#;
+
+/// Auto-registration of a producer identity (same binding rules as `AGENT_LOOKUP_SQL`).
+pub const AGENT_INSERT_SQL: &str = r#"
+    INSERT INTO agents (agent_id, agent_type, host_hostname, first_seen_at, last_seen_at, is_active)
+    VALUES ($1, $2::text::event_source_type, $3, NOW(), NOW(), true)
+"#;
+
+/// Reject agent types that are not `event_source_type` labels before any query runs.
+pub fn validate_agent_type(agent_type: &str) -> Result<&'static str, String> {
+    AGENT_TYPES
+        .iter()
+        .find(|t| **t == agent_type)
+        .copied()
+        .ok_or_else(|| format!("Invalid agent_type: {:?} (must be one of: {:?})", agent_type, AGENT_TYPES))
+}
+
+pub async fn get_or_create_agent(
+    db: &Client,
+    component_identity: &str,
+    agent_type: &str,
+) -> Result<Uuid, Box<dyn std::error::Error>> {
+    let agent_type = validate_agent_type(agent_type).map_err(|err_msg| {
+        error!("{}", err_msg);
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, err_msg)
+    })?;
+
+    // Existing agent by host_hostname (component_identity is the identifier)
+    let row = db.query_opt(AGENT_LOOKUP_SQL, &[&component_identity, &agent_type]).await.map_err(|e| {
+        error!(
+            "Database query error in get_or_create_agent | component_identity={} | agent_type={} | error={:?}",
+            component_identity, agent_type, e
+        );
+        e
+    })?;
+
+    if let Some(r) = row {
+        // Update last_seen_at
+        let agent_id: Uuid = r.get(0);
+        db.execute(
+            r#"UPDATE agents SET last_seen_at = NOW() WHERE agent_id = $1"#,
+            &[&agent_id],
+        ).await.map_err(|e| {
+            error!("Failed to update last_seen_at | agent_id={} | error={}", agent_id, e);
+            e
+        })?;
+        return Ok(agent_id);
+    }
+
+    // Create new agent
+    let agent_id = Uuid::new_v4();
+    db.execute(AGENT_INSERT_SQL, &[&agent_id, &agent_type, &component_identity]).await.map_err(|e| {
+        error!(
+            "Database INSERT error in get_or_create_agent | agent_id={} | agent_type={} | component_identity={} | error={:?}",
+            agent_id, agent_type, component_identity, e
+        );
+        e
+    })?;
+
+    info!("Registered agent | agent_id={} | component_identity={} | agent_type={}", agent_id, component_identity, agent_type);
+    Ok(agent_id)
+}
+
+// PROMPT-40A: Get or create ingestion component for audit attribution
+pub async fn get_or_create_ingestion_component(
+    db: &Client,
+) -> Result<Uuid, Box<dyn std::error::Error>> {
+    let component_name = "ransomeye_ingestion";
+    let instance_id = hostname::get()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    
+    // Try to find existing component
+    let row = db.query_opt(
+        r#"
+        SELECT component_id FROM components
+        WHERE component_type = 'core_engine'::component_type
+          AND component_name = $1
+          AND (instance_id = $2 OR (instance_id IS NULL AND $2 IS NULL))
+        LIMIT 1
+        "#,
+        &[&component_name, &instance_id],
+    ).await?;
+
+    // Embedded build provenance of this binary (commit, Cargo.lock and SBOM hashes)
+    let build = &crate::BUILD_INFO;
+    let build_metadata = build.metadata();
+
+    if let Some(r) = row {
+        let component_id: Uuid = r.get(0);
+        // Update last_heartbeat_at and the running build
+        db.execute(
+            r#"
+            UPDATE components
+            SET last_heartbeat_at = NOW(), build_hash = $2, version = $3, build_metadata = $4
+            WHERE component_id = $1
+            "#,
+            &[&component_id, &build.git_commit, &build.version, &build_metadata],
+        ).await?;
+        return Ok(component_id);
+    }
+
+    // Create new component
+    let component_id = Uuid::new_v4();
+    db.execute(
+        r#"
+        INSERT INTO components (
+            component_id, component_type, component_name, instance_id, build_hash, version, build_metadata,
+            started_at, last_heartbeat_at
+        )
+        VALUES ($1, 'core_engine'::component_type, $2, $3, $4, $5, $6, NOW(), NOW())
+        "#,
+        &[&component_id, &component_name, &instance_id, &build.git_commit, &build.version, &build_metadata],
+    ).await?;
+
+    Ok(component_id)
+}
+
+// PROMPT-40A: Insert into immutable_audit_log (fail-closed)
+/// Advisory lock key serializing immutable_audit_log chain extension across ALL writers
+/// (ingest, orchestrator, normalization worker, installer). ASCII "REAUDIT". The Rust writers use
+/// this constant; normalize.py and installer.py repeat the value.
+pub const AUDIT_CHAIN_LOCK_KEY: i64 = 0x0052_4541_5544_4954;
+
+/// Append one row to the audit hash chain. MUST be called inside an open transaction: the
+/// transaction-scoped advisory lock is held until the caller commits, so no other writer can read
+/// the chain tip before this row is visible. The lock is the only serialization: an in-process
+/// lock released before COMMIT would let a connection holding the advisory lock wait on a task
+/// waiting for that lock, a deadlock Postgres cannot see. Concurrent transactions on one
+/// connection are kept apart by PostgresStore.
+pub async fn insert_immutable_audit_log(
+    db: &Client,
+    actor_component_id: Option<Uuid>,
+    actor_agent_id: Option<Uuid>,
+    action: &str,
+    object_type: &str,
+    object_id: Option<Uuid>,
+    event_time: Option<chrono::DateTime<chrono::Utc>>,
+    payload_json: &JsonValue,
+    payload_sha256: &[u8],
+) -> Result<Uuid, Box<dyn std::error::Error>> {
+    db.execute("SELECT pg_advisory_xact_lock($1)", &[&AUDIT_CHAIN_LOCK_KEY]).await?;
+
+    // Get previous audit chain entry for hash chaining
+    let prev_row = db.query_opt(
+        r#"
+        SELECT audit_id, chain_hash_sha256, payload_sha256
+        FROM immutable_audit_log
+        ORDER BY created_at DESC, audit_id DESC
+        LIMIT 1
+        "#,
+        &[],
+    ).await?;
+
+    let (prev_audit_id, prev_chain_hash, prev_payload_sha256): (Option<Uuid>, Option<Vec<u8>>, Option<Vec<u8>>) = 
+        if let Some(row) = prev_row {
+            (
+                Some(row.get(0)),
+                Some(row.get::<usize, Vec<u8>>(1)),
+                Some(row.get::<usize, Vec<u8>>(2)),
+            )
+        } else {
+            (None, None, None)
+        };
+
+    // Compute chain hash: SHA256(prev_chain_hash || payload_sha256)
+    let prev_chain_hash_bytes = prev_chain_hash.as_deref().unwrap_or(&[0u8; 32]);
+    let mut chain_input = Vec::with_capacity(64);
+    chain_input.extend_from_slice(prev_chain_hash_bytes);
+    chain_input.extend_from_slice(payload_sha256);
+    let mut chain_hasher = Sha256::new();
+    chain_hasher.update(&chain_input);
+    let chain_hash_sha256 = chain_hasher.finalize().to_vec();
+
+    // Insert audit log entry
+    let row = db.query_one(
+        r#"
+        INSERT INTO immutable_audit_log (
+            created_at, actor_component_id, actor_agent_id, action, object_type, object_id, event_time,
+            payload_json, payload_sha256, prev_audit_id, prev_payload_sha256, chain_hash_sha256, signature_status
+        )
+        VALUES (clock_timestamp(), $1, $2, $3, $4::text::trust_object_type, $5, $6, $7, $8, $9, $10, $11, 'unknown')
+        RETURNING audit_id
+        "#,
+        &[
+            &actor_component_id,
+            &actor_agent_id,
+            &action,
+            &object_type,
+            &object_id,
+            &event_time,
+            &payload_json,
+            &payload_sha256,
+            &prev_audit_id,
+            &prev_payload_sha256,
+            &chain_hash_sha256,
+        ],
+    ).await?;
+
+    Ok(row.get(0))
+}
+
@@ -0,0 +1,1258 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/storage/sqlite.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: SQLite telemetry store for edge/standalone lab mode - single-file raw events, telemetry and hash-chained audit log without a Postgres server
+
+/*
+ * SQLite Lab Store
+ *
+ * Mirrors the Postgres tables the ingest path writes (raw_events, linux_agent_telemetry,
+ * dpi_probe_telemetry, immutable_audit_log, agents, detection_results, identity_conflicts, quarantined_events,
+ * ingest_outbox, telemetry_drops_daily, agent_drop_counters, host_inventory)
+ * with TEXT UUIDs and RFC3339 timestamps.
+ * The audit chain uses the same SHA256(prev_chain_hash || payload_sha256) link as Postgres.
+ *
+ * One connection behind an async mutex: a StorageTx owns the connection from BEGIN IMMEDIATE
+ * to COMMIT, so units of work are serialized. Statements run on the async worker; this is a
+ * lab-scale backend, not a replacement for Postgres throughput.
+ */
+
+use std::path::Path;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use crypto::digest::Sha256;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::attack;
+use super::{
+    validate_limit, AgentTypeCount, AuditRecord, ConflictResolution, DetectionRecord, DiskEncryption, DropCount, DropOrigin, DropSummary,
+    EventRate, FleetSummary, HostInventoryRecord, IdentityConflictRecord, OutboxEntry, OutboxRecord,
+    PayloadStorage, SeverityCount, QuarantinedEventRecord, RawEventRecord, SignatureValidationRecord, StorageBackend, StorageError, StorageTx, StoredRawEvent, TelemetryQuery,
+    TelemetryRecord, TelemetrySource, TelemetryStore,
+};
+
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS agents (
+    agent_id TEXT PRIMARY KEY,
+    agent_type TEXT NOT NULL,
+    host_hostname TEXT NOT NULL,
+    first_seen_at TEXT NOT NULL,
+    last_seen_at TEXT NOT NULL,
+    UNIQUE (host_hostname, agent_type)
+);
+CREATE TABLE IF NOT EXISTS raw_events (
+    raw_event_id TEXT PRIMARY KEY,
+    source_type TEXT NOT NULL,
+    source_agent_id TEXT NOT NULL REFERENCES agents(agent_id),
+    observed_at TEXT NOT NULL,
+    received_at TEXT NOT NULL,
+    event_name TEXT NOT NULL,
+    payload_json TEXT NOT NULL,
+    payload_sha256 BLOB NOT NULL,
+    payload_storage TEXT NOT NULL DEFAULT 'full' CHECK (payload_storage IN ('full', 'summary')),
+    payload_storage_reason TEXT,
+    residency_region TEXT,
+    site TEXT,
+    zone TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_raw_events_observed_at ON raw_events (observed_at);
+CREATE TABLE IF NOT EXISTS linux_agent_telemetry (
+    source_message_id TEXT PRIMARY KEY,
+    agent_id TEXT NOT NULL REFERENCES agents(agent_id),
+    source_nonce TEXT NOT NULL,
+    source_component_identity TEXT NOT NULL,
+    source_host_id TEXT NOT NULL,
+    source_signature_b64 TEXT NOT NULL,
+    source_signature_alg TEXT NOT NULL,
+    source_data_hash_hex TEXT NOT NULL,
+    observed_at TEXT NOT NULL,
+    event_name TEXT NOT NULL,
+    event_category TEXT NOT NULL,
+    pid INTEGER,
+    uid INTEGER,
+    process_name TEXT,
+    cmdline TEXT,
+    file_path TEXT,
+    network_src_ip TEXT,
+    network_dst_ip TEXT,
+    network_src_port INTEGER,
+    network_dst_port INTEGER,
+    netns_inode INTEGER,
+    host_netns INTEGER,
+    protocol TEXT,
+    container_id TEXT,
+    container_image TEXT,
+    pod_name TEXT,
+    pod_namespace TEXT,
+    payload TEXT NOT NULL,
+    payload_sha256 BLOB
+);
+CREATE TABLE IF NOT EXISTS dpi_probe_telemetry (
+    source_message_id TEXT PRIMARY KEY,
+    agent_id TEXT NOT NULL REFERENCES agents(agent_id),
+    source_nonce TEXT NOT NULL,
+    source_component_identity TEXT NOT NULL,
+    source_signature_b64 TEXT NOT NULL,
+    source_signature_alg TEXT NOT NULL,
+    source_data_hash_hex TEXT NOT NULL,
+    observed_at TEXT NOT NULL,
+    src_ip TEXT,
+    src_port INTEGER,
+    dst_ip TEXT,
+    dst_port INTEGER,
+    protocol TEXT,
+    bytes_in INTEGER,
+    bytes_out INTEGER,
+    packets_in INTEGER,
+    packets_out INTEGER,
+    tls_sni TEXT,
+    http_host TEXT,
+    http_method TEXT,
+    http_path TEXT,
+    iface_name TEXT,
+    flow_id TEXT,
+    payload TEXT NOT NULL,
+    payload_sha256 BLOB,
+    packet_count INTEGER,
+    byte_count INTEGER,
+    ja3 TEXT,
+    ja3s TEXT,
+    metadata TEXT
+);
+CREATE TABLE IF NOT EXISTS immutable_audit_log (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    audit_id TEXT NOT NULL UNIQUE,
+    created_at TEXT NOT NULL,
+    actor_component_id TEXT,
+    actor_agent_id TEXT,
+    action TEXT NOT NULL,
+    object_type TEXT NOT NULL,
+    object_id TEXT,
+    event_time TEXT,
+    payload_json TEXT NOT NULL,
+    payload_sha256 BLOB NOT NULL,
+    prev_audit_id TEXT UNIQUE,
+    prev_payload_sha256 BLOB,
+    chain_hash_sha256 BLOB NOT NULL
+);
+CREATE TABLE IF NOT EXISTS detection_results (
+    detection_id TEXT PRIMARY KEY,
+    created_at TEXT NOT NULL,
+    detection_engine TEXT NOT NULL,
+    detection_name TEXT NOT NULL,
+    detection_category TEXT,
+    severity TEXT NOT NULL,
+    confidence REAL NOT NULL CHECK (confidence >= 0.0 AND confidence <= 1.0),
+    reasoning TEXT,
+    artifacts TEXT,
+    deterministic_key BLOB NOT NULL,
+    mitre_tactic TEXT,
+    mitre_technique TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_detection_results_created_at ON detection_results (created_at);
+CREATE TABLE IF NOT EXISTS identity_conflicts (
+    conflict_id TEXT PRIMARY KEY,
+    component_type TEXT NOT NULL,
+    component_identity TEXT NOT NULL,
+    agent_id TEXT NOT NULL REFERENCES agents(agent_id),
+    first_origin TEXT NOT NULL,
+    conflicting_origin TEXT NOT NULL,
+    detection_id TEXT NOT NULL,
+    opened_at TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'open' CHECK (status IN ('open', 'resolved')),
+    resolution TEXT CHECK (resolution IN ('release', 'discard')),
+    resolution_reason TEXT,
+    resolved_at TEXT
+);
+CREATE UNIQUE INDEX IF NOT EXISTS identity_conflicts_open_uniq_idx
+ON identity_conflicts (component_type, component_identity) WHERE status = 'open';
+CREATE TABLE IF NOT EXISTS quarantined_events (
+    quarantine_id TEXT PRIMARY KEY,
+    conflict_id TEXT NOT NULL REFERENCES identity_conflicts(conflict_id),
+    component_type TEXT NOT NULL,
+    component_identity TEXT NOT NULL,
+    message_id TEXT NOT NULL,
+    source_ip TEXT,
+    host_id TEXT,
+    received_at TEXT NOT NULL,
+    signed_event TEXT NOT NULL,
+    signed_event_sha256 BLOB NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'released', 'discarded'))
+);
+CREATE INDEX IF NOT EXISTS idx_quarantined_events_conflict_id ON quarantined_events (conflict_id);
+CREATE TABLE IF NOT EXISTS ingest_outbox (
+    outbox_id TEXT PRIMARY KEY,
+    topic TEXT NOT NULL,
+    aggregate_id TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'published')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TEXT NOT NULL,
+    last_error TEXT,
+    created_at TEXT NOT NULL,
+    published_at TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_ingest_outbox_due ON ingest_outbox (status, next_attempt_at);
+CREATE TABLE IF NOT EXISTS telemetry_drops_daily (
+    agent_id TEXT NOT NULL REFERENCES agents(agent_id),
+    drop_day TEXT NOT NULL,
+    origin TEXT NOT NULL CHECK (origin IN ('agent', 'ingest')),
+    reason TEXT NOT NULL,
+    dropped INTEGER NOT NULL DEFAULT 0 CHECK (dropped >= 0),
+    first_recorded_at TEXT NOT NULL,
+    last_recorded_at TEXT NOT NULL,
+    PRIMARY KEY (agent_id, drop_day, origin, reason)
+);
+CREATE INDEX IF NOT EXISTS idx_telemetry_drops_daily_day ON telemetry_drops_daily (drop_day);
+CREATE TABLE IF NOT EXISTS agent_drop_counters (
+    agent_id TEXT NOT NULL REFERENCES agents(agent_id),
+    run_id TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    cumulative INTEGER NOT NULL CHECK (cumulative >= 0),
+    updated_at TEXT NOT NULL,
+    PRIMARY KEY (agent_id, run_id, reason)
+);
+CREATE TABLE IF NOT EXISTS host_inventory (
+    agent_id TEXT PRIMARY KEY REFERENCES agents(agent_id),
+    hostname TEXT,
+    os_name TEXT,
+    os_version TEXT,
+    kernel_release TEXT NOT NULL,
+    kernel_version TEXT,
+    arch TEXT NOT NULL,
+    cpu_model TEXT,
+    cpu_cores INTEGER NOT NULL CHECK (cpu_cores >= 0),
+    memory_total_bytes INTEGER NOT NULL CHECK (memory_total_bytes >= 0),
+    security_tools TEXT NOT NULL,
+    disk_encryption TEXT NOT NULL CHECK (disk_encryption IN ('encrypted', 'partial', 'none', 'unknown')),
+    encrypted_volumes TEXT NOT NULL,
+    source_event_id TEXT NOT NULL,
+    collected_at TEXT NOT NULL,
+    received_at TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS signature_validation_events (
+    signature_event_id TEXT PRIMARY KEY,
+    created_at TEXT NOT NULL,
+    validator_component_id TEXT,
+    object_type TEXT NOT NULL,
+    object_id TEXT NOT NULL,
+    signature_alg TEXT,
+    signature_b64 TEXT,
+    signature_status TEXT NOT NULL CHECK (signature_status IN ('valid', 'invalid', 'unknown')),
+    signer_identity TEXT,
+    cert_chain_json TEXT,
+    error_details TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_sig_validation_object ON signature_validation_events (object_type, object_id);
+CREATE TRIGGER IF NOT EXISTS immutable_audit_log_no_update
+BEFORE UPDATE ON immutable_audit_log
+BEGIN SELECT RAISE(ABORT, 'immutable_audit_log is append-only'); END;
+CREATE TRIGGER IF NOT EXISTS immutable_audit_log_no_delete
+BEFORE DELETE ON immutable_audit_log
+BEGIN SELECT RAISE(ABORT, 'immutable_audit_log is append-only'); END;
+"#;
+
+/// Fixed actor id for audit entries written by this process (no components table in lab mode).
+const LAB_INGESTION_COMPONENT_ID: Uuid = Uuid::from_u128(0x5245_494e_4745_5354_0000_0000_0000_0001);
+
+/// Fixed-width UTC (Z, microseconds) so TEXT comparison and ORDER BY are chronological.
+fn ts(t: DateTime<Utc>) -> String {
+    t.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+fn sql_err(context: &str, e: rusqlite::Error) -> StorageError {
+    StorageError::Query(format!("{}: {}", context, e))
+}
+
+fn parse_uuid(s: &str) -> Result<Uuid, StorageError> {
+    Uuid::parse_str(s).map_err(|e| StorageError::Query(format!("stored uuid '{}' invalid: {}", s, e)))
+}
+
+fn parse_time(s: &str) -> Result<DateTime<Utc>, StorageError> {
+    DateTime::parse_from_rfc3339(s)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|e| StorageError::Query(format!("stored timestamp '{}' invalid: {}", s, e)))
+}
+
+fn ensure_column(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<(), StorageError> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT 1 FROM pragma_table_info('{}') WHERE name = ?1", table))
+        .map_err(|e| sql_err("pragma table_info", e))?;
+    if stmt.exists(params![column]).map_err(|e| sql_err("pragma table_info", e))? {
+        return Ok(());
+    }
+    conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+        .map_err(|e| sql_err("schema upgrade", e))
+}
+
+const HOST_INVENTORY_COLUMNS: &str = "agent_id, hostname, os_name, os_version, kernel_release, kernel_version, arch, cpu_model, \
+     cpu_cores, memory_total_bytes, security_tools, disk_encryption, encrypted_volumes, source_event_id, collected_at, received_at";
+
+fn host_inventory_row(row: &rusqlite::Row<'_>) -> Result<HostInventoryRecord, StorageError> {
+    let get_err = |e| sql_err("host_inventory row", e);
+    let text = |i: usize| row.get::<_, String>(i).map_err(get_err);
+    let json_err = |e: serde_json::Error| StorageError::Query(format!("stored host_inventory json invalid: {}", e));
+    let disk_encryption = text(11)?;
+    Ok(HostInventoryRecord {
+        agent_id: parse_uuid(&text(0)?)?,
+        hostname: row.get(1).map_err(get_err)?,
+        os_name: row.get(2).map_err(get_err)?,
+        os_version: row.get(3).map_err(get_err)?,
+        kernel_release: text(4)?,
+        kernel_version: row.get(5).map_err(get_err)?,
+        arch: text(6)?,
+        cpu_model: row.get(7).map_err(get_err)?,
+        cpu_cores: row.get::<_, i64>(8).map_err(get_err)?.clamp(0, u32::MAX as i64) as u32,
+        memory_total_bytes: row.get::<_, i64>(9).map_err(get_err)?.max(0) as u64,
+        security_tools: serde_json::from_str(&text(10)?).map_err(json_err)?,
+        disk_encryption: DiskEncryption::parse(&disk_encryption)
+            .ok_or_else(|| StorageError::Query(format!("stored disk_encryption '{}' invalid", disk_encryption)))?,
+        encrypted_volumes: serde_json::from_str(&text(12)?).map_err(json_err)?,
+        source_event_id: text(13)?,
+        collected_at: parse_time(&text(14)?)?,
+        received_at: parse_time(&text(15)?)?,
+    })
+}
+
+pub struct SqliteStore {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteStore {
+    pub fn open(path: &Path) -> Result<Self, StorageError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| StorageError::Connection(format!("Cannot create {}: {}", parent.display(), e)))?;
+        }
+        let conn = Connection::open(path)
+            .map_err(|e| StorageError::Connection(format!("Cannot open SQLite store {}: {}", path.display(), e)))?;
+        Self::init(conn, &path.display().to_string())
+    }
+
+    /// In-memory store (tests and throwaway lab runs).
+    pub fn open_in_memory() -> Result<Self, StorageError> {
+        let conn = Connection::open_in_memory()
+            .map_err(|e| StorageError::Connection(format!("Cannot open in-memory SQLite store: {}", e)))?;
+        Self::init(conn, ":memory:")
+    }
+
+    fn init(conn: Connection, label: &str) -> Result<Self, StorageError> {
+        // journal_mode returns the resulting mode as a row
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))
+            .map_err(|e| sql_err("pragma journal_mode", e))?;
+        conn.pragma_update(None, "foreign_keys", "ON").map_err(|e| sql_err("pragma foreign_keys", e))?;
+        conn.pragma_update(None, "synchronous", "FULL").map_err(|e| sql_err("pragma synchronous", e))?;
+        conn.execute_batch(SCHEMA).map_err(|e| sql_err("schema", e))?;
+        // Lab files created before raw_events.payload_storage existed
+        ensure_column(&conn, "raw_events", "payload_storage",
+            "TEXT NOT NULL DEFAULT 'full' CHECK (payload_storage IN ('full', 'summary'))")?;
+        ensure_column(&conn, "raw_events", "payload_storage_reason", "TEXT")?;
+        for column in ["residency_region", "site", "zone"] {
+            ensure_column(&conn, "raw_events", column, "TEXT")?;
+        }
+        for column in ["container_id", "container_image", "pod_name", "pod_namespace"] {
+            ensure_column(&conn, "linux_agent_telemetry", column, "TEXT")?;
+        }
+        for column in ["network_src_port", "network_dst_port", "netns_inode", "host_netns"] {
+            ensure_column(&conn, "linux_agent_telemetry", column, "INTEGER")?;
+        }
+        for column in ["packet_count", "byte_count"] {
+            ensure_column(&conn, "dpi_probe_telemetry", column, "INTEGER")?;
+        }
+        for column in ["ja3", "ja3s", "metadata"] {
+            ensure_column(&conn, "dpi_probe_telemetry", column, "TEXT")?;
+        }
+        for column in ["mitre_tactic", "mitre_technique"] {
+            ensure_column(&conn, "detection_results", column, "TEXT")?;
+        }
+        info!("SQLite telemetry store ready ({}) - lab mode, not for production", label);
+        Ok(Self { conn: Arc::new(Mutex::new(conn)) })
+    }
+}
+
+#[async_trait]
+impl TelemetryStore for SqliteStore {
+    fn backend(&self) -> StorageBackend {
+        StorageBackend::Sqlite
+    }
+
+    async fn resolve_agent(&self, component_identity: &str, source: TelemetrySource) -> Result<Uuid, StorageError> {
+        let conn = self.conn.lock().await;
+        let now = ts(Utc::now());
+        let existing: Option<String> = conn
+            .query_row(
+                "SELECT agent_id FROM agents WHERE host_hostname = ?1 AND agent_type = ?2",
+                params![component_identity, source.as_str()],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| sql_err("agent lookup", e))?;
+        if let Some(id) = existing {
+            conn.execute("UPDATE agents SET last_seen_at = ?1 WHERE agent_id = ?2", params![now, id])
+                .map_err(|e| sql_err("agent last_seen_at", e))?;
+            return parse_uuid(&id);
+        }
+        let agent_id = Uuid::new_v4();
+        conn.execute(
+            "INSERT INTO agents (agent_id, agent_type, host_hostname, first_seen_at, last_seen_at) VALUES (?1, ?2, ?3, ?4, ?4)",
+            params![agent_id.to_string(), source.as_str(), component_identity, now],
+        )
+        .map_err(|e| sql_err("agent insert", e))?;
+        info!("Registered agent | agent_id={} | component_identity={} | agent_type={}", agent_id, component_identity, source.as_str());
+        Ok(agent_id)
+    }
+
+    async fn ingestion_component(&self) -> Result<Uuid, StorageError> {
+        Ok(LAB_INGESTION_COMPONENT_ID)
+    }
+
+    async fn begin(&self) -> Result<Box<dyn StorageTx>, StorageError> {
+        let conn = self.conn.clone().lock_owned().await;
+        conn.execute_batch("BEGIN IMMEDIATE").map_err(|e| sql_err("Failed to start transaction", e))?;
+        Ok(Box::new(SqliteTx { conn, finished: false }))
+    }
+
+    async fn query(&self, query: &TelemetryQuery) -> Result<Vec<StoredRawEvent>, StorageError> {
+        let limit = validate_limit(query.limit)?;
+        let conn = self.conn.lock().await;
+        let mut stmt = conn
+            .prepare(
+                r#"
+                SELECT raw_event_id, source_type, source_agent_id, observed_at, received_at, event_name, payload_json,
+                       payload_storage, residency_region, site, zone
+                FROM raw_events
+                WHERE (?1 IS NULL OR source_type = ?1)
+                  AND (?2 IS NULL OR source_agent_id = ?2)
+                  AND (?3 IS NULL OR observed_at >= ?3)
+                  AND (?4 IS NULL OR observed_at < ?4)
+                ORDER BY observed_at DESC
+                LIMIT ?5
+                "#,
+            )
+            .map_err(|e| sql_err("raw_events query", e))?;
+        let rows = stmt
+            .query_map(
+                params![
+                    query.source.map(|s| s.as_str()),
+                    query.agent_id.map(|a| a.to_string()),
+                    query.observed_from.map(ts),
+                    query.observed_to.map(ts),
+                    limit,
+                ],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, String>(3)?,
+                        row.get::<_, String>(4)?,
+                        row.get::<_, String>(5)?,
+                        row.get::<_, String>(6)?,
+                        row.get::<_, String>(7)?,
+                        row.get::<_, Option<String>>(8)?,
+                        row.get::<_, Option<String>>(9)?,
+                        row.get::<_, Option<String>>(10)?,
+                    ))
+                },
+            )
+            .map_err(|e| sql_err("raw_events query", e))?;
+
+        let mut events = Vec::new();
+        for row in rows {
+            let (id, source, agent_id, observed_at, received_at, event_name, payload, payload_storage, residency_region, site, zone) =
+                row.map_err(|e| sql_err("raw_events row", e))?;
+            let source = TelemetrySource::parse(&source)
+                .ok_or_else(|| StorageError::Query(format!("stored source_type '{}' invalid", source)))?;
+            events.push(StoredRawEvent {
+                raw_event_id: parse_uuid(&id)?,
+                source,
+                agent_id: parse_uuid(&agent_id)?,
+                observed_at: parse_time(&observed_at)?,
+                received_at: parse_time(&received_at)?,
+                event_name,
+                payload_json: serde_json::from_str(&payload)
+                    .map_err(|e| StorageError::Query(format!("stored payload_json invalid: {}", e)))?,
+                payload_storage: PayloadStorage::parse(&payload_storage).ok_or_else(|| {
+                    StorageError::Query(format!("stored payload_storage '{}' invalid", payload_storage))
+                })?,
+                residency_region,
+                site,
+                zone,
+            });
+        }
+        Ok(events)
+    }
+
+    async fn open_identity_conflicts(&self) -> Result<Vec<IdentityConflictRecord>, StorageError> {
+        let conn = self.conn.lock().await;
+        let mut stmt = conn
+            .prepare(
+                r#"
+                SELECT conflict_id, component_type, component_identity, agent_id, first_origin, conflicting_origin, opened_at
+                FROM identity_conflicts
+                WHERE status = 'open'
+                ORDER BY opened_at
+                "#,
+            )
+            .map_err(|e| sql_err("identity_conflicts query", e))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, String>(5)?,
+                    row.get::<_, String>(6)?,
+                ))
+            })
+            .map_err(|e| sql_err("identity_conflicts query", e))?;
+
+        let mut conflicts = Vec::new();
+        for row in rows {
+            let (id, component_type, component_identity, agent_id, first_origin, conflicting_origin, opened_at) =
+                row.map_err(|e| sql_err("identity_conflicts row", e))?;
+            let origin = |s: &str| {
+                serde_json::from_str(s).map_err(|e| StorageError::Query(format!("stored origin invalid: {}", e)))
+            };
+            conflicts.push(IdentityConflictRecord {
+                conflict_id: parse_uuid(&id)?,
+                component_type,
+                component_identity,
+                agent_id: parse_uuid(&agent_id)?,
+                first_origin: origin(&first_origin)?,
+                conflicting_origin: origin(&conflicting_origin)?,
+                opened_at: parse_time(&opened_at)?,
+            });
+        }
+        Ok(conflicts)
+    }
+
+    async fn claim_outbox(&self, limit: i64, lease: std::time::Duration) -> Result<Vec<OutboxEntry>, StorageError> {
+        let conn = self.conn.lock().await;
+        let now = Utc::now();
+        let lease = chrono::Duration::from_std(lease).map_err(|e| StorageError::InvalidInput(format!("outbox lease: {}", e)))?;
+        let mut stmt = conn
+            .prepare(
+                r#"
+                SELECT outbox_id, topic, aggregate_id, payload, attempts, created_at
+                FROM ingest_outbox
+                WHERE status = 'pending' AND next_attempt_at <= ?1
+                ORDER BY created_at
+                LIMIT ?2
+                "#,
+            )
+            .map_err(|e| sql_err("ingest_outbox claim", e))?;
+        let rows = stmt
+            .query_map(params![ts(now), limit], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, i32>(4)?,
+                    row.get::<_, String>(5)?,
+                ))
+            })
+            .map_err(|e| sql_err("ingest_outbox claim", e))?;
+        let mut entries = Vec::new();
+        for row in rows {
+            let (id, topic, aggregate_id, payload, attempts, created_at) = row.map_err(|e| sql_err("ingest_outbox row", e))?;
+            entries.push(OutboxEntry {
+                outbox_id: parse_uuid(&id)?,
+                topic,
+                aggregate_id: parse_uuid(&aggregate_id)?,
+                payload: serde_json::from_str(&payload)
+                    .map_err(|e| StorageError::Query(format!("stored outbox payload invalid: {}", e)))?,
+                attempts,
+                created_at: parse_time(&created_at)?,
+            });
+        }
+        drop(stmt);
+        // Single connection: nothing can claim between the SELECT and this lease
+        for entry in &entries {
+            conn.execute(
+                "UPDATE ingest_outbox SET next_attempt_at = ?2 WHERE outbox_id = ?1",
+                params![entry.outbox_id.to_string(), ts(now + lease)],
+            )
+            .map_err(|e| sql_err("ingest_outbox lease", e))?;
+        }
+        Ok(entries)
+    }
+
+    async fn complete_outbox(&self, outbox_id: Uuid) -> Result<(), StorageError> {
+        let conn = self.conn.lock().await;
+        conn.execute(
+            "UPDATE ingest_outbox SET status = 'published', published_at = ?2, last_error = NULL WHERE outbox_id = ?1",
+            params![outbox_id.to_string(), ts(Utc::now())],
+        )
+        .map_err(|e| sql_err("ingest_outbox complete", e))?;
+        Ok(())
+    }
+
+    async fn retry_outbox(&self, outbox_id: Uuid, error: &str, retry_in: std::time::Duration) -> Result<(), StorageError> {
+        let retry_in = chrono::Duration::from_std(retry_in).map_err(|e| StorageError::InvalidInput(format!("outbox retry: {}", e)))?;
+        let conn = self.conn.lock().await;
+        conn.execute(
+            r#"
+            UPDATE ingest_outbox SET attempts = attempts + 1, last_error = ?2, next_attempt_at = ?3
+            WHERE outbox_id = ?1 AND status = 'pending'
+            "#,
+            params![outbox_id.to_string(), error, ts(Utc::now() + retry_in)],
+        )
+        .map_err(|e| sql_err("ingest_outbox retry", e))?;
+        Ok(())
+    }
+
+    async fn purge_outbox(&self, published_before: DateTime<Utc>) -> Result<u64, StorageError> {
+        let conn = self.conn.lock().await;
+        let removed = conn
+            .execute(
+                "DELETE FROM ingest_outbox WHERE status = 'published' AND published_at < ?1",
+                params![ts(published_before)],
+            )
+            .map_err(|e| sql_err("ingest_outbox purge", e))?;
+        Ok(removed as u64)
+    }
+
+    async fn drop_summary(&self, since: NaiveDate, limit: i64) -> Result<Vec<DropSummary>, StorageError> {
+        let limit = validate_limit(limit)?;
+        let conn = self.conn.lock().await;
+        let mut stmt = conn
+            .prepare(
+                r#"
+                SELECT agent_id, drop_day, origin, reason, dropped, last_recorded_at
+                FROM telemetry_drops_daily
+                WHERE drop_day >= ?1
+                ORDER BY drop_day DESC, agent_id, origin, reason
+                LIMIT ?2
+                "#,
+            )
+            .map_err(|e| sql_err("telemetry_drops_daily query", e))?;
+        let rows = stmt
+            .query_map(params![since.to_string(), limit], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, i64>(4)?,
+                    row.get::<_, String>(5)?,
+                ))
+            })
+            .map_err(|e| sql_err("telemetry_drops_daily query", e))?;
+
+        let mut summary = Vec::new();
+        for row in rows {
+            let (agent_id, day, origin, reason, dropped, last_recorded_at) =
+                row.map_err(|e| sql_err("telemetry_drops_daily row", e))?;
+            summary.push(DropSummary {
+                agent_id: parse_uuid(&agent_id)?,
+                day: NaiveDate::parse_from_str(&day, "%Y-%m-%d")
+                    .map_err(|e| StorageError::Query(format!("stored drop_day '{}' invalid: {}", day, e)))?,
+                origin: DropOrigin::parse(&origin)
+                    .ok_or_else(|| StorageError::Query(format!("stored drop origin '{}' invalid", origin)))?,
+                reason,
+                dropped: dropped.max(0) as u64,
+                last_recorded_at: parse_time(&last_recorded_at)?,
+            });
+        }
+        Ok(summary)
+    }
+
+    async fn purge_drop_counters(&self, before: DateTime<Utc>) -> Result<u64, StorageError> {
+        let conn = self.conn.lock().await;
+        let removed = conn
+            .execute("DELETE FROM agent_drop_counters WHERE updated_at < ?1", params![ts(before)])
+            .map_err(|e| sql_err("agent_drop_counters purge", e))?;
+        Ok(removed as u64)
+    }
+
+    async fn list_host_inventory(&self, limit: i64) -> Result<Vec<HostInventoryRecord>, StorageError> {
+        let limit = validate_limit(limit)?;
+        let conn = self.conn.lock().await;
+        let mut stmt = conn
+            .prepare(&format!("SELECT {} FROM host_inventory ORDER BY agent_id LIMIT ?1", HOST_INVENTORY_COLUMNS))
+            .map_err(|e| sql_err("host_inventory query", e))?;
+        let mut rows = stmt.query(params![limit]).map_err(|e| sql_err("host_inventory query", e))?;
+        let mut inventory = Vec::new();
+        while let Some(row) = rows.next().map_err(|e| sql_err("host_inventory row", e))? {
+            inventory.push(host_inventory_row(row)?);
+        }
+        Ok(inventory)
+    }
+
+    async fn host_inventory(&self, agent_id: Uuid) -> Result<Option<HostInventoryRecord>, StorageError> {
+        let conn = self.conn.lock().await;
+        let mut stmt = conn
+            .prepare(&format!("SELECT {} FROM host_inventory WHERE agent_id = ?1", HOST_INVENTORY_COLUMNS))
+            .map_err(|e| sql_err("host_inventory lookup", e))?;
+        let mut rows = stmt.query(params![agent_id.to_string()]).map_err(|e| sql_err("host_inventory lookup", e))?;
+        match rows.next().map_err(|e| sql_err("host_inventory lookup", e))? {
+            Some(row) => host_inventory_row(row).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    async fn detection_severity_counts(&self, since: DateTime<Utc>) -> Result<Vec<SeverityCount>, StorageError> {
+        // No suppression rules in lab mode; severities are ranked as in the Postgres severity_level
+        let conn = self.conn.lock().await;
+        let mut stmt = conn
+            .prepare(
+                r#"
+                SELECT severity, count(*)
+                FROM detection_results
+                WHERE created_at >= ?1
+                GROUP BY severity
+                ORDER BY CASE severity
+                    WHEN 'critical' THEN 0 WHEN 'error' THEN 1 WHEN 'warning' THEN 2
+                    WHEN 'notice' THEN 3 WHEN 'info' THEN 4 ELSE 5 END
+                "#,
+            )
+            .map_err(|e| sql_err("detection_results severity counts", e))?;
+        let rows = stmt
+            .query_map(params![ts(since)], |row| Ok(SeverityCount { severity: row.get(0)?, detections: row.get::<_, i64>(1)?.max(0) as u64 }))
+            .map_err(|e| sql_err("detection_results severity counts", e))?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(|e| sql_err("detection_results severity row", e))
+    }
+
+    async fn event_rate(&self, since: DateTime<Utc>) -> Result<Vec<EventRate>, StorageError> {
+        // Timestamps are fixed-width text: the first 16 characters are the minute
+        let conn = self.conn.lock().await;
+        let mut stmt = conn
+            .prepare(
+                r#"
+                SELECT substr(received_at, 1, 16), count(*)
+                FROM raw_events
+                WHERE received_at >= ?1
+                GROUP BY 1
+                ORDER BY 1
+                "#,
+            )
+            .map_err(|e| sql_err("raw_events rate", e))?;
+        let rows = stmt
+            .query_map(params![ts(since)], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))
+            .map_err(|e| sql_err("raw_events rate", e))?;
+        let mut rate = Vec::new();
+        for row in rows {
+            let (minute, events) = row.map_err(|e| sql_err("raw_events rate row", e))?;
+            rate.push(EventRate { minute: parse_time(&format!("{}:00Z", minute))?, events: events.max(0) as u64 });
+        }
+        Ok(rate)
+    }
+
+    async fn fleet_summary(&self, seen_since: DateTime<Utc>) -> Result<FleetSummary, StorageError> {
+        // Lab agents are never decommissioned
+        let conn = self.conn.lock().await;
+        let mut stmt = conn
+            .prepare(
+                r#"
+                SELECT agent_type, count(*), sum(CASE WHEN last_seen_at >= ?1 THEN 1 ELSE 0 END)
+                FROM agents
+                GROUP BY agent_type
+                ORDER BY agent_type
+                "#,
+            )
+            .map_err(|e| sql_err("agents fleet summary", e))?;
+        let rows = stmt
+            .query_map(params![ts(seen_since)], |row| {
+                Ok(AgentTypeCount {
+                    agent_type: row.get(0)?,
+                    agents: row.get::<_, i64>(1)?.max(0) as u64,
+                    reporting: row.get::<_, i64>(2)?.max(0) as u64,
+                })
+            })
+            .map_err(|e| sql_err("agents fleet summary", e))?;
+        let by_type = rows.collect::<Result<Vec<_>, _>>().map_err(|e| sql_err("agents fleet summary row", e))?;
+        Ok(FleetSummary {
+            agents: by_type.iter().map(|t| t.agents).sum(),
+            decommissioned: 0,
+            reporting: by_type.iter().map(|t| t.reporting).sum(),
+            by_type,
+        })
+    }
+}
+
+struct SqliteTx {
+    conn: OwnedMutexGuard<Connection>,
+    finished: bool,
+}
+
+#[async_trait]
+impl StorageTx for SqliteTx {
+    async fn insert_raw(&mut self, raw: &RawEventRecord) -> Result<Uuid, StorageError> {
+        let raw_event_id = Uuid::new_v4();
+        self.conn
+            .execute(
+                r#"
+                INSERT INTO raw_events (raw_event_id, source_type, source_agent_id, observed_at, received_at,
+                                        event_name, payload_json, payload_sha256, payload_storage,
+                                        payload_storage_reason, residency_region, site, zone)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+                "#,
+                params![
+                    raw_event_id.to_string(),
+                    raw.source.as_str(),
+                    raw.agent_id.to_string(),
+                    ts(raw.observed_at),
+                    ts(Utc::now()),
+                    raw.event_name,
+                    raw.payload_json.get(),
+                    raw.payload_sha256,
+                    raw.payload_storage.as_str(),
+                    raw.payload_storage_reason,
+                    raw.residency_region,
+                    raw.zone.as_ref().map(|z| z.site.as_str()),
+                    raw.zone.as_ref().map(|z| z.zone.as_str()),
+                ],
+            )
+            .map_err(|e| sql_err("raw_events insert", e))?;
+        Ok(raw_event_id)
+    }
+
+    async fn insert_telemetry(&mut self, telemetry: &TelemetryRecord) -> Result<(), StorageError> {
+        match telemetry {
+            TelemetryRecord::Linux(t) => {
+                let p = &t.provenance;
+                self.conn
+                    .execute(
+                        r#"
+                        INSERT INTO linux_agent_telemetry (
+                            source_message_id, agent_id, source_nonce, source_component_identity, source_host_id,
+                            source_signature_b64, source_signature_alg, source_data_hash_hex, observed_at,
+                            event_name, event_category, pid, uid, process_name, cmdline, file_path,
+                            network_src_ip, network_dst_ip, network_src_port, network_dst_port, netns_inode, host_netns,
+                            protocol, container_id, container_image, pod_name, pod_namespace, payload, payload_sha256
+                        )
+                        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21,
+                                ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29)
+                        "#,
+                        params![
+                            p.message_id.to_string(),
+                            p.agent_id.to_string(),
+                            p.nonce,
+                            p.component_identity,
+                            t.host_id,
+                            p.signature_b64,
+                            p.signature_alg,
+                            p.data_hash_hex,
+                            ts(p.observed_at),
+                            t.event_name,
+                            t.event_category,
+                            t.pid,
+                            t.uid,
+                            t.process_name,
+                            t.cmdline,
+                            t.file_path,
+                            t.network_src_ip,
+                            t.network_dst_ip,
+                            t.network_src_port,
+                            t.network_dst_port,
+                            t.netns_inode,
+                            t.host_netns,
+                            t.protocol,
+                            t.container_id,
+                            t.container_image,
+                            t.pod_name,
+                            t.pod_namespace,
+                            t.payload_json,
+                            t.payload_sha256,
+                        ],
+                    )
+                    .map_err(|e| sql_err("linux_agent_telemetry insert", e))?;
+            }
+            TelemetryRecord::Dpi(t) => {
+                let p = &t.provenance;
+                self.conn
+                    .execute(
+                        r#"
+                        INSERT INTO dpi_probe_telemetry (
+                            source_message_id, agent_id, source_nonce, source_component_identity,
+                            source_signature_b64, source_signature_alg, source_data_hash_hex, observed_at,
+                            src_ip, src_port, dst_ip, dst_port, protocol, bytes_in, bytes_out, packets_in,
+                            packets_out, tls_sni, http_host, http_method, http_path, iface_name, flow_id,
+                            payload, payload_sha256, packet_count, byte_count, ja3, ja3s, metadata
+                        )
+                        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17,
+                                ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29, ?30)
+                        "#,
+                        params![
+                            p.message_id.to_string(),
+                            p.agent_id.to_string(),
+                            p.nonce,
+                            p.component_identity,
+                            p.signature_b64,
+                            p.signature_alg,
+                            p.data_hash_hex,
+                            ts(p.observed_at),
+                            t.src_ip,
+                            t.src_port,
+                            t.dst_ip,
+                            t.dst_port,
+                            t.protocol,
+                            t.bytes_in,
+                            t.bytes_out,
+                            t.packets_in,
+                            t.packets_out,
+                            t.tls_sni,
+                            t.http_host,
+                            t.http_method,
+                            t.http_path,
+                            t.iface_name,
+                            t.flow_id,
+                            t.payload_json,
+                            t.payload_sha256,
+                            t.packet_count,
+                            t.byte_count,
+                            t.ja3,
+                            t.ja3s,
+                            t.metadata_json,
+                        ],
+                    )
+                    .map_err(|e| sql_err("dpi_probe_telemetry insert", e))?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn append_audit(&mut self, audit: &AuditRecord) -> Result<Uuid, StorageError> {
+        // BEGIN IMMEDIATE already holds the write lock, so the chain tip cannot move under us.
+        let prev: Option<(String, Vec<u8>, Vec<u8>)> = self
+            .conn
+            .query_row(
+                "SELECT audit_id, chain_hash_sha256, payload_sha256 FROM immutable_audit_log ORDER BY seq DESC LIMIT 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()
+            .map_err(|e| sql_err("audit chain tip", e))?;
+
+        let (prev_audit_id, prev_chain_hash, prev_payload_sha256) = match prev {
+            Some((id, chain, payload)) => (Some(id), Some(chain), Some(payload)),
+            None => (None, None, None),
+        };
+
+        // Compute chain hash: SHA256(prev_chain_hash || payload_sha256)
+        let mut chain_hasher = Sha256::new();
+        chain_hasher.update(prev_chain_hash.as_deref().unwrap_or(&[0u8; 32]));
+        chain_hasher.update(&audit.payload_sha256);
+        let chain_hash_sha256 = chain_hasher.finalize().to_vec();
+
+        let audit_id = Uuid::new_v4();
+        self.conn
+            .execute(
+                r#"
+                INSERT INTO immutable_audit_log (
+                    audit_id, created_at, actor_component_id, actor_agent_id, action, object_type, object_id,
+                    event_time, payload_json, payload_sha256, prev_audit_id, prev_payload_sha256, chain_hash_sha256
+                )
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+                "#,
+                params![
+                    audit_id.to_string(),
+                    ts(Utc::now()),
+                    audit.actor_component_id.map(|x| x.to_string()),
+                    audit.actor_agent_id.map(|x| x.to_string()),
+                    audit.action,
+                    audit.object_type,
+                    audit.object_id.map(|x| x.to_string()),
+                    audit.event_time.map(ts),
+                    audit.payload_json.to_string(),
+                    audit.payload_sha256,
+                    prev_audit_id,
+                    prev_payload_sha256,
+                    chain_hash_sha256,
+                ],
+            )
+            .map_err(|e| sql_err("immutable_audit_log insert", e))?;
+        Ok(audit_id)
+    }
+
+    async fn insert_detection(&mut self, detection: &DetectionRecord) -> Result<Uuid, StorageError> {
+        let detection_id = Uuid::new_v4();
+        let attack = attack::for_detector(&detection.detection_engine, &detection.detection_name);
+        self.conn
+            .execute(
+                r#"
+                INSERT INTO detection_results (detection_id, created_at, detection_engine, detection_name,
+                                               detection_category, severity, confidence, reasoning, artifacts,
+                                               deterministic_key, mitre_tactic, mitre_technique)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+                "#,
+                params![
+                    detection_id.to_string(),
+                    ts(Utc::now()),
+                    detection.detection_engine,
+                    detection.detection_name,
+                    detection.detection_category,
+                    detection.severity,
+                    detection.confidence,
+                    detection.reasoning,
+                    detection.artifacts.to_string(),
+                    detection.deterministic_key,
+                    attack.as_ref().map(|a| a.tactic.as_str()),
+                    attack.as_ref().map(|a| a.technique_id.as_str()),
+                ],
+            )
+            .map_err(|e| sql_err("detection_results insert", e))?;
+        Ok(detection_id)
+    }
+
+    async fn insert_identity_conflict(&mut self, conflict: &IdentityConflictRecord, detection_id: Uuid) -> Result<(), StorageError> {
+        let first_origin = serde_json::to_string(&conflict.first_origin)
+            .map_err(|e| StorageError::InvalidInput(format!("first_origin: {}", e)))?;
+        let conflicting_origin = serde_json::to_string(&conflict.conflicting_origin)
+            .map_err(|e| StorageError::InvalidInput(format!("conflicting_origin: {}", e)))?;
+        self.conn
+            .execute(
+                r#"
+                INSERT INTO identity_conflicts (conflict_id, component_type, component_identity, agent_id, first_origin,
+                                                conflicting_origin, detection_id, opened_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                "#,
+                params![
+                    conflict.conflict_id.to_string(),
+                    conflict.component_type,
+                    conflict.component_identity,
+                    conflict.agent_id.to_string(),
+                    first_origin,
+                    conflicting_origin,
+                    detection_id.to_string(),
+                    ts(conflict.opened_at),
+                ],
+            )
+            .map_err(|e| sql_err("identity_conflicts insert", e))?;
+        Ok(())
+    }
+
+    async fn resolve_identity_conflict(
+        &mut self,
+        conflict_id: Uuid,
+        resolution: ConflictResolution,
+        reason: &str,
+    ) -> Result<Option<u64>, StorageError> {
+        let closed = self.conn
+            .execute(
+                r#"
+                UPDATE identity_conflicts
+                SET status = 'resolved', resolution = ?2, resolution_reason = ?3, resolved_at = ?4
+                WHERE conflict_id = ?1 AND status = 'open'
+                "#,
+                params![conflict_id.to_string(), resolution.as_str(), reason, ts(Utc::now())],
+            )
+            .map_err(|e| sql_err("identity_conflicts resolve", e))?;
+        if closed == 0 {
+            return Ok(None);
+        }
+        let event_status = match resolution {
+            ConflictResolution::Release => "released",
+            ConflictResolution::Discard => "discarded",
+        };
+        let events = self.conn
+            .execute(
+                "UPDATE quarantined_events SET status = ?2 WHERE conflict_id = ?1 AND status = 'pending'",
+                params![conflict_id.to_string(), event_status],
+            )
+            .map_err(|e| sql_err("quarantined_events resolve", e))?;
+        Ok(Some(events as u64))
+    }
+
+    async fn insert_quarantined_event(&mut self, event: &QuarantinedEventRecord) -> Result<Uuid, StorageError> {
+        let quarantine_id = Uuid::new_v4();
+        self.conn
+            .execute(
+                r#"
+                INSERT INTO quarantined_events (quarantine_id, conflict_id, component_type, component_identity, message_id,
+                                                source_ip, host_id, received_at, signed_event, signed_event_sha256)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+                "#,
+                params![
+                    quarantine_id.to_string(),
+                    event.conflict_id.to_string(),
+                    event.component_type,
+                    event.component_identity,
+                    event.message_id,
+                    event.origin.source_ip.map(|ip| ip.to_string()),
+                    event.origin.host_id,
+                    ts(Utc::now()),
+                    event.signed_event.get(),
+                    event.signed_event_sha256,
+                ],
+            )
+            .map_err(|e| sql_err("quarantined_events insert", e))?;
+        Ok(quarantine_id)
+    }
+
+    async fn enqueue_outbox(&mut self, entry: &OutboxRecord) -> Result<Uuid, StorageError> {
+        let outbox_id = Uuid::new_v4();
+        let now = ts(Utc::now());
+        self.conn
+            .execute(
+                r#"
+                INSERT INTO ingest_outbox (outbox_id, topic, aggregate_id, payload, next_attempt_at, created_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?5)
+                "#,
+                params![outbox_id.to_string(), entry.topic, entry.aggregate_id.to_string(), entry.payload.to_string(), now],
+            )
+            .map_err(|e| sql_err("ingest_outbox insert", e))?;
+        Ok(outbox_id)
+    }
+
+    async fn insert_signature_validation(
+        &mut self,
+        validator_component_id: Uuid,
+        record: &SignatureValidationRecord,
+    ) -> Result<Uuid, StorageError> {
+        let signature_event_id = Uuid::new_v4();
+        self.conn
+            .execute(
+                r#"
+                INSERT INTO signature_validation_events (signature_event_id, created_at, validator_component_id, object_type, object_id,
+                                                         signature_alg, signature_b64, signature_status, signer_identity,
+                                                         cert_chain_json, error_details)
+                VALUES (?1, ?2, ?3, 'raw_event', ?4, 'Ed25519', ?5, ?6, ?7, ?8, ?9)
+                "#,
+                params![
+                    signature_event_id.to_string(),
+                    ts(Utc::now()),
+                    validator_component_id.to_string(),
+                    record.object_id.to_string(),
+                    record.signature_b64,
+                    record.status,
+                    record.signer_identity,
+                    record.context.to_string(),
+                    record.error_details,
+                ],
+            )
+            .map_err(|e| sql_err("signature_validation_events insert", e))?;
+        Ok(signature_event_id)
+    }
+
+    async fn add_drops(&mut self, drops: &[DropCount]) -> Result<(), StorageError> {
+        let now = ts(Utc::now());
+        for drop in drops.iter().filter(|d| d.dropped > 0) {
+            self.conn
+                .execute(
+                    r#"
+                    INSERT INTO telemetry_drops_daily (agent_id, drop_day, origin, reason, dropped, first_recorded_at, last_recorded_at)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)
+                    ON CONFLICT (agent_id, drop_day, origin, reason)
+                    DO UPDATE SET dropped = dropped + excluded.dropped, last_recorded_at = excluded.last_recorded_at
+                    "#,
+                    params![
+                        drop.agent_id.to_string(),
+                        drop.day.to_string(),
+                        drop.origin.as_str(),
+                        drop.reason,
+                        drop.dropped.min(i64::MAX as u64) as i64,
+                        now,
+                    ],
+                )
+                .map_err(|e| sql_err("telemetry_drops_daily upsert", e))?;
+        }
+        Ok(())
+    }
+
+    async fn advance_drop_counter(
+        &mut self,
+        agent_id: Uuid,
+        run_id: &str,
+        reason: &str,
+        cumulative: u64,
+    ) -> Result<u64, StorageError> {
+        let cumulative = cumulative.min(i64::MAX as u64) as i64;
+        let previous: Option<i64> = self
+            .conn
+            .query_row(
+                "SELECT cumulative FROM agent_drop_counters WHERE agent_id = ?1 AND run_id = ?2 AND reason = ?3",
+                params![agent_id.to_string(), run_id, reason],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| sql_err("agent_drop_counters lookup", e))?;
+        self.conn
+            .execute(
+                r#"
+                INSERT INTO agent_drop_counters (agent_id, run_id, reason, cumulative, updated_at)
+                VALUES (?1, ?2, ?3, ?4, ?5)
+                ON CONFLICT (agent_id, run_id, reason)
+                DO UPDATE SET cumulative = MAX(cumulative, excluded.cumulative), updated_at = excluded.updated_at
+                "#,
+                params![agent_id.to_string(), run_id, reason, cumulative, ts(Utc::now())],
+            )
+            .map_err(|e| sql_err("agent_drop_counters upsert", e))?;
+        Ok((cumulative - previous.unwrap_or(0)).max(0) as u64)
+    }
+
+    async fn upsert_host_inventory(&mut self, inventory: &HostInventoryRecord) -> Result<bool, StorageError> {
+        let json_err = |e: serde_json::Error| StorageError::InvalidInput(format!("host_inventory not serializable: {}", e));
+        let written = self
+            .conn
+            .execute(
+                r#"
+                INSERT INTO host_inventory (agent_id, hostname, os_name, os_version, kernel_release, kernel_version, arch, cpu_model,
+                                            cpu_cores, memory_total_bytes, security_tools, disk_encryption, encrypted_volumes,
+                                            source_event_id, collected_at, received_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)
+                ON CONFLICT (agent_id) DO UPDATE SET
+                    hostname = excluded.hostname, os_name = excluded.os_name, os_version = excluded.os_version,
+                    kernel_release = excluded.kernel_release, kernel_version = excluded.kernel_version, arch = excluded.arch,
+                    cpu_model = excluded.cpu_model, cpu_cores = excluded.cpu_cores, memory_total_bytes = excluded.memory_total_bytes,
+                    security_tools = excluded.security_tools, disk_encryption = excluded.disk_encryption,
+                    encrypted_volumes = excluded.encrypted_volumes, source_event_id = excluded.source_event_id,
+                    collected_at = excluded.collected_at, received_at = excluded.received_at
+                WHERE host_inventory.collected_at <= excluded.collected_at
+                "#,
+                params![
+                    inventory.agent_id.to_string(),
+                    inventory.hostname,
+                    inventory.os_name,
+                    inventory.os_version,
+                    inventory.kernel_release,
+                    inventory.kernel_version,
+                    inventory.arch,
+                    inventory.cpu_model,
+                    inventory.cpu_cores as i64,
+                    inventory.memory_total_bytes.min(i64::MAX as u64) as i64,
+                    serde_json::to_string(&inventory.security_tools).map_err(json_err)?,
+                    inventory.disk_encryption.as_str(),
+                    serde_json::to_string(&inventory.encrypted_volumes).map_err(json_err)?,
+                    inventory.source_event_id,
+                    ts(inventory.collected_at),
+                    ts(inventory.received_at),
+                ],
+            )
+            .map_err(|e| sql_err("host_inventory upsert", e))?;
+        Ok(written > 0)
+    }
+
+    async fn commit(mut self: Box<Self>) -> Result<(), StorageError> {
+        // A failed COMMIT leaves the transaction open; Drop rolls it back.
+        self.conn.execute_batch("COMMIT").map_err(|e| sql_err("Failed to commit transaction", e))?;
+        self.finished = true;
+        Ok(())
+    }
+
+    async fn rollback(mut self: Box<Self>) {
+        self.finished = true;
+        if let Err(e) = self.conn.execute_batch("ROLLBACK") {
+            error!("Failed to roll back SQLite transaction: {}", e);
+        }
+    }
+}
+
+impl Drop for SqliteTx {
+    fn drop(&mut self) {
+        // Unit of work abandoned (early return / panic): never leave the shared connection mid-transaction.
+        if !self.finished {
+            if let Err(e) = self.conn.execute_batch("ROLLBACK") {
+                error!("Failed to roll back abandoned SQLite transaction: {}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,292 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/suppression.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Detection suppression - signed, expiring suppression rules, their validation and matching, and the in-transaction check that stores a suppressed detection without raising its alert
+
+/*
+ * Detection Suppression
+ *
+ * SOC teams silence known-benign detections with suppression rules. A rule is a JSON document
+ * signed (Ed25519) with an operator key that ingest trusts (RANSOMEYE_INGEST_SUPPRESSION_SIGNERS,
+ * comma-separated hex public keys):
+ *
+ *   {
+ *     "name":       "backup-window-rate-budget",
+ *     "match":      { "detection_engine": "ingest_rate_budget", "max_severity": "warning",
+ *                     "artifacts": { "component_id": "backup-01" } },
+ *     "reason":     "Nightly backup bursts (CHG-1234)",
+ *     "created_by": "soc-lead",
+ *     "expires_at": "2026-12-31T00:00:00Z"
+ *   }
+ *
+ * POST /admin/suppressions carries the document text exactly as signed, the signature and the
+ * signer key. A rule needs a criterion other than max_severity, a reason and an expiry at most
+ * MAX_LIFETIME_DAYS ahead. A document is accepted once (rule_sha256 is unique), so an expired
+ * rule cannot be replayed; extending one means signing a new document.
+ *
+ * Every criterion present must hold: detection_engine, detection_name and detection_category
+ * compare exactly, max_severity bounds the severity, and each artifacts entry must equal the
+ * detection's top-level artifact of that name. A detection matched by an active rule (not past
+ * expires_at, not expired early through POST /admin/suppressions/expire) is still written to
+ * detection_results, with suppression_id set, and counted on the rule (hit_count, last_hit_at);
+ * no detection.created webhook is queued for it, so it raises no alert. Matching runs inside the
+ * detection's transaction and re-verifies each rule's signature: a stored rule that no longer
+ * verifies suppresses nothing. Suppression needs the Postgres control plane.
+ */
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, Utc};
+use crypto::signature::{verify_ed25519, ED25519_PUBLIC_KEY_LEN, ED25519_SIGNATURE_LEN};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use tokio_postgres::Client;
+use tracing::error;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::storage::DetectionRecord;
+
+/// Longest a rule may stay active
+pub const MAX_LIFETIME_DAYS: i64 = 90;
+const MAX_FIELD_BYTES: usize = 256;
+const MAX_ARTIFACT_CRITERIA: usize = 16;
+
+/// severity_level labels, lowest first
+pub const SEVERITY_LEVELS: &[&str] = &["debug", "info", "notice", "warning", "error", "critical"];
+
+fn severity_rank(label: &str) -> Option<usize> {
+    SEVERITY_LEVELS.iter().position(|l| *l == label)
+}
+
+/// Match criteria of a rule; every criterion present must hold.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SuppressionMatch {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detection_engine: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detection_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detection_category: Option<String>,
+    /// Highest severity suppressed (severity_level label)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_severity: Option<String>,
+    /// Top-level artifact name -> value it must equal
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[schema(value_type = Object)]
+    pub artifacts: BTreeMap<String, JsonValue>,
+}
+
+impl SuppressionMatch {
+    pub fn matches(&self, detection: &DetectionRecord) -> bool {
+        let exact = |want: &Option<String>, have: Option<&str>| want.as_deref().is_none_or(|w| Some(w) == have);
+        if !exact(&self.detection_engine, Some(&detection.detection_engine))
+            || !exact(&self.detection_name, Some(&detection.detection_name))
+            || !exact(&self.detection_category, detection.detection_category.as_deref())
+        {
+            return false;
+        }
+        if let Some(max) = self.max_severity.as_deref().and_then(severity_rank) {
+            match severity_rank(&detection.severity) {
+                Some(rank) if rank <= max => {}
+                _ => return false,
+            }
+        }
+        self.artifacts.iter().all(|(name, value)| detection.artifacts.get(name) == Some(value))
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let narrowing = self.detection_engine.is_some()
+            || self.detection_name.is_some()
+            || self.detection_category.is_some()
+            || !self.artifacts.is_empty();
+        if !narrowing {
+            return Err("match needs detection_engine, detection_name, detection_category or artifacts".to_string());
+        }
+        for (field, value) in [
+            ("detection_engine", &self.detection_engine),
+            ("detection_name", &self.detection_name),
+            ("detection_category", &self.detection_category),
+        ] {
+            if let Some(v) = value {
+                check_text(field, v)?;
+            }
+        }
+        if let Some(max) = &self.max_severity {
+            if severity_rank(max).is_none() {
+                return Err(format!("max_severity '{}' is not one of {}", max, SEVERITY_LEVELS.join("|")));
+            }
+        }
+        if self.artifacts.len() > MAX_ARTIFACT_CRITERIA {
+            return Err(format!("at most {} artifacts criteria", MAX_ARTIFACT_CRITERIA));
+        }
+        for (name, value) in &self.artifacts {
+            check_text("artifacts name", name)?;
+            match value {
+                JsonValue::String(s) => check_text(name, s)?,
+                JsonValue::Number(_) | JsonValue::Bool(_) => {}
+                _ => return Err(format!("artifacts.{} must be a string, number or boolean", name)),
+            }
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn check_text(field: &str, value: &str) -> Result<(), String> {
+    if value.trim().is_empty() {
+        return Err(format!("{} is empty", field));
+    }
+    if value.len() > MAX_FIELD_BYTES {
+        return Err(format!("{} exceeds {} bytes", field, MAX_FIELD_BYTES));
+    }
+    Ok(())
+}
+
+/// Signed rule document.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SuppressionRule {
+    pub name: String,
+    #[serde(rename = "match")]
+    pub criteria: SuppressionMatch,
+    /// Why the pattern is benign (ticket or change reference)
+    pub reason: String,
+    pub created_by: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl SuppressionRule {
+    /// Parse a document as signed and check it can be activated at `now`.
+    pub fn parse(document: &str, now: DateTime<Utc>) -> Result<Self, String> {
+        let rule: SuppressionRule = serde_json::from_str(document).map_err(|e| format!("invalid rule document: {}", e))?;
+        check_text("name", &rule.name)?;
+        check_text("created_by", &rule.created_by)?;
+        if rule.reason.trim().is_empty() {
+            return Err("reason is empty".to_string());
+        }
+        rule.criteria.validate()?;
+        if rule.expires_at <= now {
+            return Err(format!("expires_at {} is not in the future", rule.expires_at.to_rfc3339()));
+        }
+        if rule.expires_at > now + Duration::days(MAX_LIFETIME_DAYS) {
+            return Err(format!("expires_at is more than {} days ahead", MAX_LIFETIME_DAYS));
+        }
+        Ok(rule)
+    }
+}
+
+/// Keys trusted to sign suppression rules (RANSOMEYE_INGEST_SUPPRESSION_SIGNERS).
+#[derive(Debug, Clone, Default)]
+pub struct SuppressionSigners {
+    keys: Vec<[u8; ED25519_PUBLIC_KEY_LEN]>,
+}
+
+impl SuppressionSigners {
+    /// No signers configured: existing rules still apply, new ones are refused.
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(&std::env::var("RANSOMEYE_INGEST_SUPPRESSION_SIGNERS").unwrap_or_default())
+            .map_err(|e| format!("RANSOMEYE_INGEST_SUPPRESSION_SIGNERS: {}", e))
+    }
+
+    /// Comma-separated hex Ed25519 public keys.
+    pub fn parse(list: &str) -> Result<Self, String> {
+        let mut keys = Vec::new();
+        for entry in list.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            keys.push(parse_public_key(entry)?);
+        }
+        Ok(Self { keys })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn is_trusted(&self, key: &[u8]) -> bool {
+        self.keys.iter().any(|k| k.as_slice() == key)
+    }
+
+    /// Signature of `document` by a trusted signer.
+    pub fn verify(&self, signer_key: &[u8], document: &[u8], signature: &[u8]) -> Result<(), String> {
+        if !self.is_trusted(signer_key) {
+            return Err(format!("signer {} is not a trusted suppression signer", hex::encode(signer_key)));
+        }
+        if signature.len() != ED25519_SIGNATURE_LEN {
+            return Err(format!("signature must be {} bytes", ED25519_SIGNATURE_LEN));
+        }
+        verify_ed25519(signer_key, document, signature).map_err(|_| "signature does not verify".to_string())
+    }
+}
+
+/// Hex Ed25519 public key.
+pub fn parse_public_key(value: &str) -> Result<[u8; ED25519_PUBLIC_KEY_LEN], String> {
+    let bytes = hex::decode(value).map_err(|_| format!("'{}' is not hex", value))?;
+    bytes
+        .try_into()
+        .map_err(|_| format!("'{}' is not a {}-byte Ed25519 public key", value, ED25519_PUBLIC_KEY_LEN))
+}
+
+/// One rule as listed by the admin API.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Suppression {
+    pub suppression_id: Uuid,
+    pub name: String,
+    #[serde(rename = "match")]
+    pub criteria: SuppressionMatch,
+    pub reason: String,
+    pub created_by: String,
+    /// Hex Ed25519 public key that signed the rule
+    pub signer_key: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub expired_at: Option<DateTime<Utc>>,
+    pub expired_by: Option<String>,
+    pub expire_reason: Option<String>,
+    /// Detections suppressed so far
+    pub hit_count: i64,
+    pub last_hit_at: Option<DateTime<Utc>>,
+    /// Suppressing now: not past expires_at and not expired early
+    pub active: bool,
+}
+
+/// Active rule matching `detection`, with the hit counted on it. Runs on the detection's
+/// transaction, so the count commits with the suppressed detection.
+pub async fn apply(db: &Client, detection: &DetectionRecord) -> Result<Option<Uuid>, tokio_postgres::Error> {
+    let rows = db
+        .query(
+            r#"
+            SELECT suppression_id, rule_json, signature, signer_key
+            FROM detection_suppressions
+            WHERE expired_at IS NULL AND expires_at > now()
+            ORDER BY created_at, suppression_id
+            "#,
+            &[],
+        )
+        .await?;
+    for row in rows {
+        let suppression_id: Uuid = row.get(0);
+        let document: String = row.get(1);
+        let signature: Vec<u8> = row.get(2);
+        let signer_key: Vec<u8> = row.get(3);
+        // An altered rule must not silence anything
+        if verify_ed25519(&signer_key, document.as_bytes(), &signature).is_err() {
+            error!("FAIL-CLOSED: suppression {} does not match its signature; ignored", suppression_id);
+            continue;
+        }
+        let rule: SuppressionRule = match serde_json::from_str(&document) {
+            Ok(rule) => rule,
+            Err(e) => {
+                error!("FAIL-CLOSED: suppression {} has an unreadable rule; ignored: {}", suppression_id, e);
+                continue;
+            }
+        };
+        if rule.criteria.matches(detection) {
+            db.execute(
+                "UPDATE detection_suppressions SET hit_count = hit_count + 1, last_hit_at = now() WHERE suppression_id = $1",
+                &[&suppression_id],
+            )
+            .await?;
+            return Ok(Some(suppression_id));
+        }
+    }
+    Ok(None)
+}
@@ -0,0 +1,529 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/topology.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Site/zone topology of multi-segment deployments - the signed YAML document of sites, zones and sensor placement, the zone labels of ingested events and the cross-zone lateral movement check
+
+/*
+ * Site / Zone Topology
+ *
+ * Large estates are split into sites (a campus, a data centre) and zones inside them (office LAN,
+ * server VLAN, OT segment, DMZ), each watched by its own agents and probes. The topology is a YAML
+ * document signed (Ed25519) as exact bytes:
+ *
+ *   RANSOMEYE_TOPOLOGY_PATH      topology document, e.g. /etc/ransomeye/topology.yaml
+ *   <path>.sig                   {"signer_key": "<hex Ed25519 public key>", "signature": "<hex signature>"}
+ *   RANSOMEYE_TOPOLOGY_SIGNERS   comma-separated hex public keys trusted to sign it
+ *
+ *   version: 1
+ *   sites:
+ *     - id: hq
+ *       name: Headquarters
+ *       zones:
+ *         - id: hq-office
+ *           cidrs: [10.10.0.0/16]
+ *           sensors: [ws-0142, ws-0143]
+ *         - id: hq-servers
+ *           cidrs: [10.20.0.0/16, 10.21.0.0/16]
+ *           sensors: [dpi-hq-core]
+ *   lateral_movement:
+ *     ports: [22, 135, 445, 3389, 5985, 5986]
+ *     allowed:
+ *       - { from: hq-admin, to: hq-servers }
+ *
+ * Sensors are listed by component identity (envelope component_id) and sit in exactly one zone.
+ * Every event a placed sensor sends is labelled with its site and zone (raw_events.site / zone,
+ * telemetry.accepted); events of sensors the document does not place carry no label. Zone
+ * networks place the far end of a connection by address; the most specific network wins, and
+ * the same network may not belong to two zones.
+ *
+ * Cross-zone lateral movement: a connection to a remote administration port (lateral_movement
+ * ports; SMB, RDP, WinRM, SSH and RPC by default) from one zone into another is reported as a
+ * cross_zone_lateral_movement detection unless the document allows that directed zone pair.
+ * For agent events the agent's own end is in the agent's zone; for DPI flows both ends are
+ * placed by address. The same source, destination and port are reported once per
+ * RANSOMEYE_TOPOLOGY_LATERAL_DEDUP_SECS (default 3600). Events are never refused.
+ *
+ * Without RANSOMEYE_TOPOLOGY_PATH nothing is labelled or checked. A configured document that
+ * does not verify against a trusted key or does not validate stops ingest from starting.
+ */
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::net::IpAddr;
+use std::path::Path;
+use std::time::{Duration as StdDuration, Instant};
+
+use crypto::digest::Sha256;
+use crypto::signature::{verify_ed25519, ED25519_PUBLIC_KEY_LEN, ED25519_SIGNATURE_LEN};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+
+/// Topology document format this build understands
+pub const TOPOLOGY_VERSION: u32 = 1;
+/// Appended to RANSOMEYE_TOPOLOGY_PATH for the signature file
+pub const SIGNATURE_SUFFIX: &str = ".sig";
+/// SSH, RPC endpoint mapper, SMB, RDP, WinRM (HTTP and HTTPS)
+pub const DEFAULT_LATERAL_PORTS: &[u16] = &[22, 135, 445, 3389, 5985, 5986];
+pub const DEFAULT_LATERAL_DEDUP_SECS: u64 = 3600;
+/// Site and zone ids: lowercase letters, digits and '-', starting with a letter
+pub const MAX_ID_LEN: usize = 64;
+const MAX_DOCUMENT_BYTES: u64 = 4 * 1024 * 1024;
+const MAX_NAME_BYTES: usize = 256;
+/// Reported (source, destination, port) triples remembered for deduplication
+const MAX_REPORTED: usize = 100_000;
+
+/// An IP network (address masked to its prefix)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    /// The network of `ip` with `prefix` bits (clamped to the address width)
+    pub fn of(ip: IpAddr, prefix: u8) -> Self {
+        match ip {
+            IpAddr::V4(v4) => {
+                let prefix = prefix.min(32);
+                let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+                Self { network: IpAddr::V4((u32::from(v4) & mask).into()), prefix }
+            }
+            IpAddr::V6(v6) => {
+                let prefix = prefix.min(128);
+                let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+                Self { network: IpAddr::V6((u128::from(v6) & mask).into()), prefix }
+            }
+        }
+    }
+
+    /// "10.0.0.0/8"; host bits must be zero
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let (addr, prefix) = s.trim().split_once('/').ok_or_else(|| format!("'{}' is not a CIDR (addr/prefix)", s))?;
+        let addr: IpAddr = addr.parse().map_err(|_| format!("'{}' has an invalid address", s))?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = prefix
+            .parse::<u8>()
+            .ok()
+            .filter(|p| *p <= max)
+            .ok_or_else(|| format!("'{}' has an invalid prefix (0-{})", s, max))?;
+        let cidr = Self::of(addr, prefix);
+        if cidr.network != addr {
+            return Err(format!("'{}' has host bits set (network is {})", s, cidr));
+        }
+        Ok(cidr)
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        ip.is_ipv4() == self.network.is_ipv4() && Self::of(ip, self.prefix) == *self
+    }
+
+    pub fn prefix(&self) -> u8 {
+        self.prefix
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix)
+    }
+}
+
+fn default_lateral_ports() -> Vec<u16> {
+    DEFAULT_LATERAL_PORTS.to_vec()
+}
+
+/// Topology document as signed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TopologyDocument {
+    pub version: u32,
+    pub sites: Vec<SiteSpec>,
+    #[serde(default)]
+    pub lateral_movement: LateralMovementSpec,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SiteSpec {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub zones: Vec<ZoneSpec>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ZoneSpec {
+    /// Unique across all sites
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Networks of the zone's hosts
+    #[serde(default)]
+    pub cidrs: Vec<String>,
+    /// Component identities of the agents and probes placed in the zone
+    #[serde(default)]
+    pub sensors: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LateralMovementSpec {
+    /// Remote administration ports a cross-zone connection is checked on
+    #[serde(default = "default_lateral_ports")]
+    pub ports: Vec<u16>,
+    /// Directed zone pairs where such connections are expected (jump hosts, management zones)
+    #[serde(default)]
+    pub allowed: Vec<ZoneRoute>,
+}
+
+impl Default for LateralMovementSpec {
+    fn default() -> Self {
+        Self { ports: default_lateral_ports(), allowed: Vec::new() }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ZoneRoute {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TopologySignature {
+    pub signer_key: String,
+    pub signature: String,
+}
+
+/// Site and zone an event, sensor or address belongs to
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub struct ZoneLabel {
+    pub site: String,
+    pub zone: String,
+}
+
+fn validate_id(kind: &str, id: &str) -> Result<(), String> {
+    let mut chars = id.chars();
+    let valid = id.len() <= MAX_ID_LEN
+        && chars.next().is_some_and(|c| c.is_ascii_lowercase())
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if !valid {
+        return Err(format!(
+            "invalid {} id '{}' (lowercase letters, digits and '-', starting with a letter, at most {} chars)",
+            kind, id, MAX_ID_LEN
+        ));
+    }
+    Ok(())
+}
+
+fn check_name(field: &str, value: &str) -> Result<(), String> {
+    if value.trim().is_empty() {
+        return Err(format!("{} is empty", field));
+    }
+    if value.len() > MAX_NAME_BYTES {
+        return Err(format!("{} exceeds {} bytes", field, MAX_NAME_BYTES));
+    }
+    Ok(())
+}
+
+/// One end of a connection seen in an event
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Endpoint<'a> {
+    pub addr: Option<IpAddr>,
+    pub port: Option<u16>,
+    /// Zone of the sensor on this end (the agent's own host); otherwise placed by address
+    pub sensor_zone: Option<&'a ZoneLabel>,
+}
+
+/// A connection to an administration port from one zone into another that is not allowed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CrossZoneMovement {
+    pub source: ZoneLabel,
+    pub destination: ZoneLabel,
+    pub source_addr: Option<IpAddr>,
+    pub destination_addr: Option<IpAddr>,
+    pub port: u16,
+}
+
+/// Validated topology.
+#[derive(Debug, Clone)]
+pub struct Topology {
+    /// Hex SHA-256 of the document bytes
+    pub document_sha256: String,
+    zones: HashMap<String, ZoneLabel>,
+    sensors: HashMap<String, ZoneLabel>,
+    /// Most specific network first
+    networks: Vec<(Cidr, ZoneLabel)>,
+    sites: usize,
+    lateral_ports: BTreeSet<u16>,
+    allowed: HashSet<(String, String)>,
+}
+
+impl Topology {
+    /// Parse and validate a document (its signature is checked by `load_signed`).
+    pub fn parse(document: &str) -> Result<Self, String> {
+        let doc: TopologyDocument = serde_yaml::from_str(document).map_err(|e| format!("invalid topology document: {}", e))?;
+        if doc.version != TOPOLOGY_VERSION {
+            return Err(format!("topology version {} is not supported (expected {})", doc.version, TOPOLOGY_VERSION));
+        }
+        if doc.sites.is_empty() {
+            return Err("topology has no sites".to_string());
+        }
+        let mut site_ids = HashSet::new();
+        let mut zones = HashMap::new();
+        let mut sensors = HashMap::new();
+        let mut networks: Vec<(Cidr, ZoneLabel)> = Vec::new();
+        for site in &doc.sites {
+            validate_id("site", &site.id)?;
+            if !site_ids.insert(site.id.as_str()) {
+                return Err(format!("site '{}' is listed twice", site.id));
+            }
+            if let Some(name) = &site.name {
+                check_name(&format!("site {} name", site.id), name)?;
+            }
+            if site.zones.is_empty() {
+                return Err(format!("site '{}' has no zones", site.id));
+            }
+            for zone in &site.zones {
+                validate_id("zone", &zone.id)?;
+                if let Some(name) = &zone.name {
+                    check_name(&format!("zone {} name", zone.id), name)?;
+                }
+                let label = ZoneLabel { site: site.id.clone(), zone: zone.id.clone() };
+                if zones.insert(zone.id.clone(), label.clone()).is_some() {
+                    return Err(format!("zone '{}' is listed twice", zone.id));
+                }
+                for cidr in &zone.cidrs {
+                    let cidr = Cidr::parse(cidr).map_err(|e| format!("zone {}: {}", zone.id, e))?;
+                    if let Some((_, other)) = networks.iter().find(|(c, _)| *c == cidr) {
+                        return Err(format!("network {} is in zones '{}' and '{}'", cidr, other.zone, zone.id));
+                    }
+                    networks.push((cidr, label.clone()));
+                }
+                for sensor in &zone.sensors {
+                    check_name(&format!("zone {} sensor", zone.id), sensor)?;
+                    if let Some(other) = sensors.insert(sensor.clone(), label.clone()) {
+                        return Err(format!("sensor '{}' is placed in zones '{}' and '{}'", sensor, other.zone, zone.id));
+                    }
+                }
+            }
+        }
+        if doc.lateral_movement.ports.contains(&0) {
+            return Err("lateral_movement port 0 is not a port".to_string());
+        }
+        let mut allowed = HashSet::new();
+        for route in &doc.lateral_movement.allowed {
+            for zone in [&route.from, &route.to] {
+                if !zones.contains_key(zone) {
+                    return Err(format!("lateral_movement allows unknown zone '{}'", zone));
+                }
+            }
+            if route.from == route.to {
+                return Err(format!("lateral_movement route {0} -> {0} is within one zone", route.from));
+            }
+            allowed.insert((route.from.clone(), route.to.clone()));
+        }
+        networks.sort_by_key(|n| std::cmp::Reverse(n.0.prefix));
+        Ok(Self {
+            document_sha256: hex::encode(Sha256::digest(document.as_bytes())),
+            zones,
+            sensors,
+            networks,
+            sites: site_ids.len(),
+            lateral_ports: doc.lateral_movement.ports.iter().copied().collect(),
+            allowed,
+        })
+    }
+
+    pub fn site_count(&self) -> usize {
+        self.sites
+    }
+
+    pub fn zone_count(&self) -> usize {
+        self.zones.len()
+    }
+
+    pub fn sensor_count(&self) -> usize {
+        self.sensors.len()
+    }
+
+    /// Zone a sensor (component identity) is placed in
+    pub fn sensor_zone(&self, component_id: &str) -> Option<&ZoneLabel> {
+        self.sensors.get(component_id)
+    }
+
+    /// Zone of the most specific network holding `ip`
+    pub fn address_zone(&self, ip: IpAddr) -> Option<&ZoneLabel> {
+        self.networks.iter().find(|(cidr, _)| cidr.contains(ip)).map(|(_, label)| label)
+    }
+
+    fn endpoint_zone<'a>(&'a self, end: &Endpoint<'a>) -> Option<&'a ZoneLabel> {
+        end.sensor_zone.or_else(|| end.addr.and_then(|ip| self.address_zone(ip)))
+    }
+
+    /// Connection between `a` and `b` that is lateral movement across zones. The end listening on
+    /// an administration port is the destination (b's port is checked first).
+    pub fn lateral_movement(&self, a: &Endpoint<'_>, b: &Endpoint<'_>) -> Option<CrossZoneMovement> {
+        let admin = |end: &Endpoint<'_>| end.port.filter(|p| self.lateral_ports.contains(p));
+        let (source, destination, port) = match (admin(b), admin(a)) {
+            (Some(port), _) => (a, b, port),
+            (None, Some(port)) => (b, a, port),
+            (None, None) => return None,
+        };
+        let from = self.endpoint_zone(source)?;
+        let to = self.endpoint_zone(destination)?;
+        if from == to || self.allowed.contains(&(from.zone.clone(), to.zone.clone())) {
+            return None;
+        }
+        Some(CrossZoneMovement {
+            source: from.clone(),
+            destination: to.clone(),
+            source_addr: source.addr,
+            destination_addr: destination.addr,
+            port,
+        })
+    }
+}
+
+/// Keys trusted to sign the topology (RANSOMEYE_TOPOLOGY_SIGNERS).
+#[derive(Debug, Clone, Default)]
+pub struct TopologySigners {
+    keys: Vec<[u8; ED25519_PUBLIC_KEY_LEN]>,
+}
+
+impl TopologySigners {
+    /// Comma-separated hex Ed25519 public keys.
+    pub fn parse(list: &str) -> Result<Self, String> {
+        let keys = list
+            .split(',')
+            .map(str::trim)
+            .filter(|e| !e.is_empty())
+            .map(|entry| {
+                hex::decode(entry)
+                    .ok()
+                    .and_then(|b| b.try_into().ok())
+                    .ok_or_else(|| format!("'{}' is not a hex Ed25519 public key", entry))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { keys })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Signature of `document` by a trusted signer.
+    pub fn verify(&self, document: &[u8], signature: &TopologySignature) -> Result<(), String> {
+        let key = hex::decode(&signature.signer_key).map_err(|_| "signer_key is not hex".to_string())?;
+        if !self.keys.iter().any(|k| k.as_slice() == key.as_slice()) {
+            return Err(format!("signer {} is not a trusted topology signer", signature.signer_key));
+        }
+        let sig = hex::decode(&signature.signature).map_err(|_| "signature is not hex".to_string())?;
+        if sig.len() != ED25519_SIGNATURE_LEN {
+            return Err(format!("signature must be {} bytes", ED25519_SIGNATURE_LEN));
+        }
+        verify_ed25519(&key, document, &sig).map_err(|_| "topology signature does not verify".to_string())
+    }
+}
+
+fn read_limited(path: &Path) -> Result<Vec<u8>, String> {
+    let len = std::fs::metadata(path).map_err(|e| format!("{}: {}", path.display(), e))?.len();
+    if len > MAX_DOCUMENT_BYTES {
+        return Err(format!("{} exceeds {} bytes", path.display(), MAX_DOCUMENT_BYTES));
+    }
+    std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Read the document at `path` and its `<path>.sig`, verify and parse it.
+pub fn load_signed(path: &Path, signers: &TopologySigners) -> Result<Topology, String> {
+    let document = read_limited(path)?;
+    let mut sig_path = path.as_os_str().to_owned();
+    sig_path.push(SIGNATURE_SUFFIX);
+    let sig_path = Path::new(&sig_path);
+    let signature: TopologySignature = serde_json::from_slice(&read_limited(sig_path)?)
+        .map_err(|e| format!("{}: invalid signature file: {}", sig_path.display(), e))?;
+    signers.verify(&document, &signature)?;
+    let document = std::str::from_utf8(&document).map_err(|_| format!("{} is not UTF-8", path.display()))?;
+    Topology::parse(document)
+}
+
+/// Topology of this ingest instance and the lateral movement already reported.
+#[derive(Debug)]
+pub struct TopologyState {
+    topology: Option<Topology>,
+    dedup_window: StdDuration,
+    reported: DashMap<String, Instant>,
+}
+
+impl TopologyState {
+    pub fn new(topology: Option<Topology>, dedup_window: StdDuration) -> Self {
+        Self { topology, dedup_window, reported: DashMap::new() }
+    }
+
+    /// No topology: nothing is labelled or checked.
+    pub fn disabled() -> Self {
+        Self::new(None, StdDuration::from_secs(DEFAULT_LATERAL_DEDUP_SECS))
+    }
+
+    /// RANSOMEYE_TOPOLOGY_PATH (unset: disabled), RANSOMEYE_TOPOLOGY_SIGNERS,
+    /// RANSOMEYE_TOPOLOGY_LATERAL_DEDUP_SECS. FAIL-CLOSED on a document that does not verify.
+    pub fn from_env() -> Result<Self, String> {
+        let dedup_secs = match std::env::var("RANSOMEYE_TOPOLOGY_LATERAL_DEDUP_SECS") {
+            Ok(v) => v
+                .parse::<u64>()
+                .ok()
+                .filter(|s| *s > 0)
+                .ok_or_else(|| format!("Invalid RANSOMEYE_TOPOLOGY_LATERAL_DEDUP_SECS '{}'", v))?,
+            Err(_) => DEFAULT_LATERAL_DEDUP_SECS,
+        };
+        let path = match std::env::var("RANSOMEYE_TOPOLOGY_PATH") {
+            Ok(p) if !p.trim().is_empty() => p,
+            _ => return Ok(Self::disabled()),
+        };
+        let signers = TopologySigners::parse(&std::env::var("RANSOMEYE_TOPOLOGY_SIGNERS").unwrap_or_default())
+            .map_err(|e| format!("RANSOMEYE_TOPOLOGY_SIGNERS: {}", e))?;
+        if signers.is_empty() {
+            return Err("FAIL-CLOSED: RANSOMEYE_TOPOLOGY_PATH is set but RANSOMEYE_TOPOLOGY_SIGNERS is not".to_string());
+        }
+        let topology = load_signed(Path::new(&path), &signers).map_err(|e| format!("FAIL-CLOSED: topology {}: {}", path, e))?;
+        Ok(Self::new(Some(topology), StdDuration::from_secs(dedup_secs)))
+    }
+
+    pub fn topology(&self) -> Option<&Topology> {
+        self.topology.as_ref()
+    }
+
+    /// Label of the events of `component_id`
+    pub fn label(&self, component_id: &str) -> Option<ZoneLabel> {
+        self.topology.as_ref()?.sensor_zone(component_id).cloned()
+    }
+
+    /// Lateral movement between `a` and `b` not reported within the dedup window
+    pub fn check_lateral(&self, a: &Endpoint<'_>, b: &Endpoint<'_>, now: Instant) -> Option<CrossZoneMovement> {
+        let movement = self.topology.as_ref()?.lateral_movement(a, b)?;
+        let addr = |ip: Option<IpAddr>| ip.map(|ip| ip.to_string()).unwrap_or_default();
+        let key = format!(
+            "{}|{}|{}|{}|{}",
+            movement.source.zone,
+            movement.destination.zone,
+            addr(movement.source_addr),
+            addr(movement.destination_addr),
+            movement.port
+        );
+        if let Some(at) = self.reported.get(&key) {
+            if now.duration_since(*at) < self.dedup_window {
+                return None;
+            }
+        }
+        if self.reported.len() >= MAX_REPORTED {
+            let window = self.dedup_window;
+            self.reported.retain(|_, at| now.duration_since(*at) < window);
+        }
+        // Still full: report without remembering rather than miss a movement
+        if self.reported.len() < MAX_REPORTED {
+            self.reported.insert(key, now);
+        }
+        Some(movement)
+    }
+}
@@ -0,0 +1,233 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/webhooks.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Lifecycle webhooks - event types, subscription validation, HMAC-SHA256 payload signing, retry schedule and the transactional delivery outbox (webhook_deliveries)
+
+/*
+ * Lifecycle Webhooks
+ *
+ * Operators register HTTPS endpoints with an event-type filter (POST /admin/webhooks); each
+ * subscription gets its own signing secret, returned once. Producers never call endpoints
+ * directly: they enqueue one webhook_deliveries row per matching subscription, in the same
+ * transaction as the change they report where one exists (detection_results inserts). The
+ * orchestrator's webhook dispatcher delivers due rows, retries failures on RetryPolicy and moves
+ * rows that exhaust their attempts to status 'dead' (the dead-letter set, re-drivable by an
+ * operator).
+ *
+ * Every delivery is a POST of the event JSON with:
+ *   X-RansomEye-Event:     event type
+ *   X-RansomEye-Delivery:  delivery_id (stable across retries; receivers dedupe on it)
+ *   X-RansomEye-Signature: t=<unix seconds>,v1=<hex HMAC-SHA256(secret, "<t>.<body>")>
+ *
+ * Webhooks need the Postgres control plane; the SQLite lab backend has no outbox.
+ */
+
+use std::net::IpAddr;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use crypto::hmac::HmacSha256Key;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use tokio_postgres::types::Json;
+use tokio_postgres::Client;
+use url::Url;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+pub const AGENT_ENROLLED: &str = "agent.enrolled";
+pub const DETECTION_CREATED: &str = "detection.created";
+pub const RETENTION_RUN: &str = "retention.run";
+pub const RETENTION_ALERT: &str = "retention.alert";
+pub const DISK_QUOTA: &str = "disk.quota";
+pub const DB_CAPACITY: &str = "db.capacity";
+pub const OPERATOR_BREAK_GLASS: &str = "operator.break_glass";
+
+/// Every event type a subscription may filter on ("*" subscribes to all of them).
+pub const EVENT_TYPES: &[&str] =
+    &[AGENT_ENROLLED, DETECTION_CREATED, RETENTION_RUN, RETENTION_ALERT, DISK_QUOTA, DB_CAPACITY, OPERATOR_BREAK_GLASS];
+pub const ALL_EVENTS: &str = "*";
+
+pub const SIGNATURE_HEADER: &str = "X-RansomEye-Signature";
+pub const EVENT_HEADER: &str = "X-RansomEye-Event";
+pub const DELIVERY_HEADER: &str = "X-RansomEye-Delivery";
+
+/// Lifecycle event as delivered (the request body).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebhookEvent {
+    pub event_id: Uuid,
+    pub event_type: String,
+    pub occurred_at: DateTime<Utc>,
+    pub data: JsonValue,
+}
+
+impl WebhookEvent {
+    pub fn new(event_type: &str, data: JsonValue) -> Self {
+        Self {
+            event_id: Uuid::new_v4(),
+            event_type: event_type.to_string(),
+            occurred_at: Utc::now(),
+            data,
+        }
+    }
+}
+
+/// webhook_deliveries.status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    /// Waiting for its first or next attempt
+    Pending,
+    Delivered,
+    /// Attempts exhausted (dead letter)
+    Dead,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Dead => "dead",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(DeliveryStatus::Pending),
+            "delivered" => Some(DeliveryStatus::Delivered),
+            "dead" => Some(DeliveryStatus::Dead),
+            _ => None,
+        }
+    }
+}
+
+/// Backoff between attempts: base * 2^(attempt-1), capped; dead after max_attempts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 8,
+            base_delay: Duration::from_secs(30),
+            max_delay: Duration::from_secs(3600),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before the next attempt after `attempts` failed ones; `None` once the delivery is dead.
+    pub fn next_delay(&self, attempts: u32) -> Option<Duration> {
+        if attempts >= self.max_attempts {
+            return None;
+        }
+        let factor = 1u32.checked_shl(attempts.saturating_sub(1)).unwrap_or(u32::MAX);
+        Some(self.base_delay.saturating_mul(factor).min(self.max_delay))
+    }
+}
+
+/// Subscription as listed to operators (the secret is never returned after registration).
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct WebhookSubscription {
+    pub webhook_id: Uuid,
+    pub url: String,
+    pub event_types: Vec<String>,
+    pub description: Option<String>,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// One delivery attempt record (delivery history).
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct WebhookDelivery {
+    pub delivery_id: Uuid,
+    pub webhook_id: Uuid,
+    pub event_id: Uuid,
+    pub event_type: String,
+    pub status: DeliveryStatus,
+    pub attempts: i32,
+    pub last_status_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+/// Validate a subscription target and filter. HTTPS is required; plain HTTP only to loopback
+/// receivers (local relays, tests).
+pub fn validate_subscription(url: &str, event_types: &[String]) -> Result<(), String> {
+    let parsed = Url::parse(url).map_err(|e| format!("invalid url: {}", e))?;
+    let loopback = match parsed.host() {
+        Some(url::Host::Domain(d)) => d.eq_ignore_ascii_case("localhost"),
+        Some(url::Host::Ipv4(ip)) => IpAddr::V4(ip).is_loopback(),
+        Some(url::Host::Ipv6(ip)) => IpAddr::V6(ip).is_loopback(),
+        None => return Err("url has no host".to_string()),
+    };
+    match parsed.scheme() {
+        "https" => {}
+        "http" if loopback => {}
+        other => return Err(format!("url scheme '{}' not allowed (https required)", other)),
+    }
+    if !parsed.username().is_empty() || parsed.password().is_some() {
+        return Err("credentials in url are not allowed; receivers verify the signature".to_string());
+    }
+    if event_types.is_empty() {
+        return Err("event_types must not be empty".to_string());
+    }
+    if let Some(bad) = event_types
+        .iter()
+        .find(|t| t.as_str() != ALL_EVENTS && !EVENT_TYPES.contains(&t.as_str()))
+    {
+        return Err(format!("unknown event type '{}' (expected one of {} or *)", bad, EVENT_TYPES.join(", ")));
+    }
+    Ok(())
+}
+
+/// X-RansomEye-Signature value for a body sent at `timestamp` (unix seconds).
+pub fn sign(secret: &[u8], timestamp: i64, body: &[u8]) -> String {
+    let key = HmacSha256Key::new(secret);
+    let tag = key.sign_parts(&[timestamp.to_string().as_bytes(), b".", body]);
+    format!("t={},v1={}", timestamp, hex::encode(tag))
+}
+
+/// Receiver-side check of an X-RansomEye-Signature header (constant time). `max_skew` bounds
+/// replay of captured deliveries.
+pub fn verify(secret: &[u8], header: &str, body: &[u8], now: i64, max_skew: Duration) -> bool {
+    let mut timestamp = None;
+    let mut signature = None;
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", v)) => timestamp = v.parse::<i64>().ok(),
+            Some(("v1", v)) => signature = hex::decode(v).ok(),
+            _ => {}
+        }
+    }
+    let (Some(timestamp), Some(signature)) = (timestamp, signature) else {
+        return false;
+    };
+    if now.abs_diff(timestamp) > max_skew.as_secs() {
+        return false;
+    }
+    let mut message = format!("{}.", timestamp).into_bytes();
+    message.extend_from_slice(body);
+    HmacSha256Key::new(secret).verify(&message, &signature).is_ok()
+}
+
+/// Enqueue one delivery per enabled subscription whose filter matches. Run it on the connection
+/// (and inside the transaction) that writes the reported change. Returns deliveries queued.
+pub async fn enqueue(db: &Client, event: &WebhookEvent) -> Result<u64, tokio_postgres::Error> {
+    db.execute(
+        r#"
+        INSERT INTO webhook_deliveries (delivery_id, webhook_id, event_id, event_type, payload)
+        SELECT gen_random_uuid(), webhook_id, $1, $2, $3
+        FROM webhook_subscriptions
+        WHERE enabled AND ($2 = ANY(event_types) OR '*' = ANY(event_types))
+        "#,
+        &[&event.event_id, &event.event_type, &Json(event)],
+    )
+    .await
+}
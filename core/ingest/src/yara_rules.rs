@@ -0,0 +1,246 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/src/yara_rules.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: YARA rule distribution - signed rule pack documents and their validation, on-demand scan requests, and the orders handed to Linux agents over the control channel
+
+/*
+ * YARA Rule Distribution
+ *
+ * Linux agents scan files with YARA rules they receive from Core. Rules travel as a pack: a JSON
+ * document signed (Ed25519) with an operator key that ingest trusts
+ * (RANSOMEYE_INGEST_YARA_SIGNERS, comma-separated hex public keys):
+ *
+ *   {
+ *     "name":       "ransomware-families",
+ *     "version":    42,
+ *     "rules":      "rule LockBit_Note { strings: $a = \"Restore-My-Files.txt\" condition: $a }",
+ *     "reason":     "Weekly threat intel refresh (CHG-2211)",
+ *     "created_by": "threat-intel"
+ *   }
+ *
+ * POST /admin/yara-rule-packs carries the document text exactly as signed, the signature and the
+ * signer key. Versions only go up: a pack whose version does not exceed every earlier pack is
+ * refused, so an old pack cannot be rolled back in. Agents are handed the highest version and
+ * verify the signature again against their own pinned keys (AGENT_YARA_SIGNERS), so ingest
+ * cannot change a rule on its way to a host.
+ *
+ * Operators ask one agent for an immediate scan through POST /admin/yara-scans (paths, or the
+ * agent's own scan paths). Agents poll POST /agents/yara/poll with the pack version they hold;
+ * the answer carries the current pack when it is newer and claims the agent's pending scan, if
+ * any. A scan not claimed within CLAIM_WINDOW_SECS expires. Matches arrive as yara_match events
+ * through /ingest/linux and scan summaries with agent_stats. Needs the Postgres control plane.
+ */
+
+use chrono::{DateTime, Utc};
+use crypto::signature::{verify_ed25519, ED25519_PUBLIC_KEY_LEN, ED25519_SIGNATURE_LEN};
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::suppression::parse_public_key;
+
+/// Largest rule source a pack may carry
+pub const MAX_RULES_BYTES: usize = 4 * 1024 * 1024;
+/// Most paths one scan request may name
+pub const MAX_SCAN_PATHS: usize = 32;
+/// A scan no agent claimed within this window expires
+pub const CLAIM_WINDOW_SECS: i64 = 24 * 3600;
+const MAX_FIELD_BYTES: usize = 256;
+const MAX_PATH_BYTES: usize = 4096;
+
+fn check_text(field: &str, value: &str) -> Result<(), String> {
+    if value.trim().is_empty() {
+        return Err(format!("{} is empty", field));
+    }
+    if value.len() > MAX_FIELD_BYTES {
+        return Err(format!("{} exceeds {} bytes", field, MAX_FIELD_BYTES));
+    }
+    Ok(())
+}
+
+/// Signed rule pack document.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct YaraRulePack {
+    pub name: String,
+    /// Monotonic: each pack must exceed every earlier one
+    pub version: i64,
+    /// YARA rule source
+    pub rules: String,
+    /// Why the pack was issued (ticket or change reference)
+    pub reason: String,
+    pub created_by: String,
+}
+
+impl YaraRulePack {
+    /// Parse a document as signed and check it can be distributed.
+    pub fn parse(document: &str) -> Result<Self, String> {
+        let pack: YaraRulePack =
+            serde_json::from_str(document).map_err(|e| format!("invalid rule pack document: {}", e))?;
+        check_text("name", &pack.name)?;
+        check_text("created_by", &pack.created_by)?;
+        if pack.reason.trim().is_empty() {
+            return Err("reason is empty".to_string());
+        }
+        if pack.version < 1 {
+            return Err(format!("version {} is not positive", pack.version));
+        }
+        if !pack.rules.contains("rule") {
+            return Err("rules contain no YARA rule".to_string());
+        }
+        if pack.rules.len() > MAX_RULES_BYTES {
+            return Err(format!("rules exceed {} bytes", MAX_RULES_BYTES));
+        }
+        Ok(pack)
+    }
+}
+
+/// Keys trusted to sign YARA rule packs (RANSOMEYE_INGEST_YARA_SIGNERS).
+#[derive(Debug, Clone, Default)]
+pub struct YaraSigners {
+    keys: Vec<[u8; ED25519_PUBLIC_KEY_LEN]>,
+}
+
+impl YaraSigners {
+    /// No signers configured: the current pack is still handed out, new ones are refused.
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(&std::env::var("RANSOMEYE_INGEST_YARA_SIGNERS").unwrap_or_default())
+            .map_err(|e| format!("RANSOMEYE_INGEST_YARA_SIGNERS: {}", e))
+    }
+
+    /// Comma-separated hex Ed25519 public keys.
+    pub fn parse(list: &str) -> Result<Self, String> {
+        let mut keys = Vec::new();
+        for entry in list.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            keys.push(parse_public_key(entry)?);
+        }
+        Ok(Self { keys })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Signature of `document` by a trusted signer.
+    pub fn verify(&self, signer_key: &[u8], document: &[u8], signature: &[u8]) -> Result<(), String> {
+        if !self.keys.iter().any(|k| k.as_slice() == signer_key) {
+            return Err(format!("signer {} is not a trusted YARA signer", hex::encode(signer_key)));
+        }
+        if signature.len() != ED25519_SIGNATURE_LEN {
+            return Err(format!("signature must be {} bytes", ED25519_SIGNATURE_LEN));
+        }
+        verify_ed25519(signer_key, document, signature).map_err(|_| "signature does not verify".to_string())
+    }
+}
+
+/// POST /admin/yara-scans body.
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct ScanRequest {
+    pub agent_id: Uuid,
+    /// Absolute paths to scan; empty scans the paths configured on the agent
+    #[serde(default)]
+    pub paths: Vec<String>,
+    /// Case or incident reference
+    pub reason: String,
+    pub requested_by: String,
+}
+
+impl ScanRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        check_text("requested_by", &self.requested_by)?;
+        if self.reason.trim().is_empty() {
+            return Err("reason is empty".to_string());
+        }
+        if self.paths.len() > MAX_SCAN_PATHS {
+            return Err(format!("at most {} paths", MAX_SCAN_PATHS));
+        }
+        for path in &self.paths {
+            if !path.starts_with('/') || path.len() > MAX_PATH_BYTES {
+                return Err(format!("path '{}' is not an absolute path", path));
+            }
+            if path.split('/').any(|part| part == "..") || path.contains('\0') {
+                return Err(format!("path '{}' is not canonical", path));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Current pack as handed to an agent; the agent verifies it before use.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RulePackOrder {
+    pub pack_id: Uuid,
+    pub version: i64,
+    /// Pack document exactly as signed
+    pub document: String,
+    /// Base64 Ed25519 signature over the UTF-8 bytes of `document`
+    pub signature: String,
+    /// Hex Ed25519 public key of the signer
+    pub signer_key: String,
+}
+
+/// On-demand scan claimed by the agent.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ScanOrder {
+    pub scan_id: Uuid,
+    /// Empty: the paths configured on the agent
+    pub paths: Vec<String>,
+}
+
+/// POST /agents/yara/poll body.
+#[derive(Debug, Clone, Default, Deserialize, ToSchema)]
+pub struct YaraPollRequest {
+    /// Version of the pack the agent runs (absent: none yet)
+    #[serde(default)]
+    pub pack_version: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct YaraPollResponse {
+    /// Newer pack than the agent's; absent when it is current
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pack: Option<RulePackOrder>,
+    /// Scan to run now
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scan: Option<ScanOrder>,
+}
+
+/// One rule pack as listed by the admin API (without the rules).
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct YaraRulePackEntry {
+    pub pack_id: Uuid,
+    pub name: String,
+    pub version: i64,
+    /// Hex SHA-256 of the signed document
+    pub document_sha256: String,
+    /// Hex Ed25519 public key that signed the pack
+    pub signer_key: String,
+    pub rules_bytes: i32,
+    pub reason: String,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    /// Highest version: what agents are handed
+    pub current: bool,
+}
+
+/// One scan request as listed by the admin API.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct YaraScan {
+    pub scan_id: Uuid,
+    pub agent_id: Uuid,
+    pub paths: Vec<String>,
+    pub reason: String,
+    pub requested_by: String,
+    pub status: String,
+    pub requested_at: DateTime<Utc>,
+    pub claimed_at: Option<DateTime<Utc>>,
+}
+
+/// Expire scan requests not claimed within CLAIM_WINDOW_SECS.
+pub async fn expire_stale(db: &Client) -> Result<u64, tokio_postgres::Error> {
+    db.execute(
+        "UPDATE yara_scan_requests SET status = 'expired' WHERE status = 'requested' AND requested_at < now() - make_interval(secs => $1)",
+        &[&(CLAIM_WINDOW_SECS as f64)],
+    )
+    .await
+}
@@ -0,0 +1,185 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/agent_cache_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for the agent identity cache - hits within the TTL, expiry, short-lived negative entries on store failures, and invalidation on enrollment changes
+
+/*
+ * Agent Cache Tests
+ *
+ * A counting store wraps the SQLite lab backend: repeated events of one identity resolve against
+ * the store once per TTL, failures are replayed from cache only for the negative TTL, and
+ * invalidation forces the next event back to the store.
+ */
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::time::{Duration, Instant};
+
+    use async_trait::async_trait;
+    use uuid::Uuid;
+
+    use ingest::agent_cache::AgentIdentityCache;
+    use chrono::{DateTime, NaiveDate, Utc};
+    use ingest::storage::{
+        DropSummary, EventRate, FleetSummary, HostInventoryRecord, IdentityConflictRecord, OutboxEntry, SeverityCount, SqliteStore, StorageBackend,
+        StorageError, StorageTx, StoredRawEvent, TelemetryQuery, TelemetrySource, TelemetryStore,
+    };
+
+    const TTL: Duration = Duration::from_secs(300);
+    const NEGATIVE_TTL: Duration = Duration::from_secs(5);
+
+    struct CountingStore {
+        inner: SqliteStore,
+        resolves: AtomicUsize,
+        failing: AtomicBool,
+    }
+
+    impl CountingStore {
+        fn new() -> Self {
+            Self { inner: SqliteStore::open_in_memory().unwrap(), resolves: AtomicUsize::new(0), failing: AtomicBool::new(false) }
+        }
+
+        fn resolves(&self) -> usize {
+            self.resolves.load(Ordering::SeqCst)
+        }
+    }
+
+    #[async_trait]
+    impl TelemetryStore for CountingStore {
+        fn backend(&self) -> StorageBackend {
+            self.inner.backend()
+        }
+
+        async fn resolve_agent(&self, component_identity: &str, source: TelemetrySource) -> Result<Uuid, StorageError> {
+            self.resolves.fetch_add(1, Ordering::SeqCst);
+            if self.failing.load(Ordering::SeqCst) {
+                return Err(StorageError::Connection("store unavailable".to_string()));
+            }
+            self.inner.resolve_agent(component_identity, source).await
+        }
+
+        async fn ingestion_component(&self) -> Result<Uuid, StorageError> {
+            self.inner.ingestion_component().await
+        }
+
+        async fn begin(&self) -> Result<Box<dyn StorageTx>, StorageError> {
+            self.inner.begin().await
+        }
+
+        async fn query(&self, query: &TelemetryQuery) -> Result<Vec<StoredRawEvent>, StorageError> {
+            self.inner.query(query).await
+        }
+
+        async fn open_identity_conflicts(&self) -> Result<Vec<IdentityConflictRecord>, StorageError> {
+            self.inner.open_identity_conflicts().await
+        }
+
+        async fn claim_outbox(&self, limit: i64, lease: Duration) -> Result<Vec<OutboxEntry>, StorageError> {
+            self.inner.claim_outbox(limit, lease).await
+        }
+
+        async fn complete_outbox(&self, outbox_id: Uuid) -> Result<(), StorageError> {
+            self.inner.complete_outbox(outbox_id).await
+        }
+
+        async fn retry_outbox(&self, outbox_id: Uuid, error: &str, retry_in: Duration) -> Result<(), StorageError> {
+            self.inner.retry_outbox(outbox_id, error, retry_in).await
+        }
+
+        async fn purge_outbox(&self, published_before: DateTime<Utc>) -> Result<u64, StorageError> {
+            self.inner.purge_outbox(published_before).await
+        }
+
+        async fn drop_summary(&self, since: NaiveDate, limit: i64) -> Result<Vec<DropSummary>, StorageError> {
+            self.inner.drop_summary(since, limit).await
+        }
+
+        async fn purge_drop_counters(&self, before: DateTime<Utc>) -> Result<u64, StorageError> {
+            self.inner.purge_drop_counters(before).await
+        }
+
+        async fn list_host_inventory(&self, limit: i64) -> Result<Vec<HostInventoryRecord>, StorageError> {
+            self.inner.list_host_inventory(limit).await
+        }
+
+        async fn host_inventory(&self, agent_id: Uuid) -> Result<Option<HostInventoryRecord>, StorageError> {
+            self.inner.host_inventory(agent_id).await
+        }
+
+        async fn detection_severity_counts(&self, since: DateTime<Utc>) -> Result<Vec<SeverityCount>, StorageError> {
+            self.inner.detection_severity_counts(since).await
+        }
+
+        async fn event_rate(&self, since: DateTime<Utc>) -> Result<Vec<EventRate>, StorageError> {
+            self.inner.event_rate(since).await
+        }
+
+        async fn fleet_summary(&self, seen_since: DateTime<Utc>) -> Result<FleetSummary, StorageError> {
+            self.inner.fleet_summary(seen_since).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hits_within_ttl_and_reresolves_after_expiry() {
+        let store = CountingStore::new();
+        let cache = AgentIdentityCache::new(TTL, NEGATIVE_TTL, 100);
+        let t0 = Instant::now();
+
+        let agent_id = cache.resolve_at(&store, "host-1", TelemetrySource::LinuxAgent, t0).await.unwrap();
+        for i in 1..50 {
+            let again = cache.resolve_at(&store, "host-1", TelemetrySource::LinuxAgent, t0 + Duration::from_secs(i)).await.unwrap();
+            assert_eq!(again, agent_id);
+        }
+        assert_eq!(store.resolves(), 1);
+
+        // Same identity, other producer type: a separate agents row
+        let dpi = cache.resolve_at(&store, "host-1", TelemetrySource::DpiProbe, t0).await.unwrap();
+        assert_ne!(dpi, agent_id);
+        assert_eq!(store.resolves(), 2);
+
+        let after = cache.resolve_at(&store, "host-1", TelemetrySource::LinuxAgent, t0 + TTL).await.unwrap();
+        assert_eq!(after, agent_id);
+        assert_eq!(store.resolves(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_failures_are_cached_only_for_negative_ttl() {
+        let store = CountingStore::new();
+        let cache = AgentIdentityCache::new(TTL, NEGATIVE_TTL, 100);
+        let t0 = Instant::now();
+
+        store.failing.store(true, Ordering::SeqCst);
+        assert!(cache.resolve_at(&store, "host-1", TelemetrySource::LinuxAgent, t0).await.is_err());
+        assert!(cache.resolve_at(&store, "host-1", TelemetrySource::LinuxAgent, t0 + Duration::from_secs(1)).await.is_err());
+        assert_eq!(store.resolves(), 1);
+
+        store.failing.store(false, Ordering::SeqCst);
+        let agent_id = cache.resolve_at(&store, "host-1", TelemetrySource::LinuxAgent, t0 + NEGATIVE_TTL).await.unwrap();
+        assert_eq!(store.resolves(), 2);
+
+        // A fresh agent_id keeps being served
+        assert_eq!(cache.resolve_at(&store, "host-1", TelemetrySource::LinuxAgent, t0 + NEGATIVE_TTL * 2).await.unwrap(), agent_id);
+        assert_eq!(store.resolves(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_invalidation_and_bound() {
+        let store = CountingStore::new();
+        let cache = AgentIdentityCache::new(TTL, NEGATIVE_TTL, 2);
+        let t0 = Instant::now();
+
+        let agent_id = cache.resolve_at(&store, "host-1", TelemetrySource::LinuxAgent, t0).await.unwrap();
+        cache.invalidate_identity("host-1");
+        assert!(cache.is_empty());
+        assert_eq!(cache.resolve_at(&store, "host-1", TelemetrySource::LinuxAgent, t0).await.unwrap(), agent_id);
+        assert_eq!(store.resolves(), 2);
+
+        cache.invalidate_agent(agent_id);
+        assert!(cache.is_empty());
+
+        cache.resolve_at(&store, "host-1", TelemetrySource::LinuxAgent, t0).await.unwrap();
+        cache.resolve_at(&store, "host-2", TelemetrySource::LinuxAgent, t0).await.unwrap();
+        cache.resolve_at(&store, "host-3", TelemetrySource::LinuxAgent, t0).await.unwrap();
+        assert!(cache.len() <= 2);
+    }
+}
@@ -0,0 +1,160 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/agent_log_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for sealed agent log segments - line chain and seal verification, continuity against stored segments (genesis, linked, gap, fork, key change, duplicates) and spooling
+
+/*
+ * Agent Log Segment Tests
+ *
+ * Segments are built here the way the agent seals them: any edited, dropped or reordered line
+ * breaks the head hash, any edited seal field breaks the signature, and a segment that
+ * contradicts a stored neighbour is refused while a missing neighbour is only a gap.
+ */
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose, Engine as _};
+    use chrono::Utc;
+    use crypto::signature::Ed25519KeyPair;
+    use ingest::agent_log::{self, Continuity, LogSegment, Placement, SpoolEntry, StoredSegment};
+    use tempfile::TempDir;
+    use uuid::Uuid;
+
+    const CHAIN: &str = "5d3c1e2a-8f4b-4c6d-9e0a-1b2c3d4e5f60";
+    const BOOT: &str = "0f1e2d3c-4b5a-6978-8796-a5b4c3d2e1f0";
+
+    fn sealed(key: &Ed25519KeyPair, index: u64, first_line: u64, prev_hash: &str, lines: &[&str]) -> LogSegment {
+        let lines: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+        let head_hash = lines.iter().fold(prev_hash.to_string(), |prev, line| agent_log::line_hash(&prev, line));
+        let mut segment = LogSegment {
+            chain_id: CHAIN.to_string(),
+            boot_id: BOOT.to_string(),
+            segment_index: index,
+            first_line,
+            prev_hash: prev_hash.to_string(),
+            head_hash,
+            sealed_at: "2026-03-01T12:00:00.000000Z".to_string(),
+            segments_dropped_before: 0,
+            lines,
+            signer_key: hex::encode(key.public_key()),
+            signature: String::new(),
+        };
+        segment.signature = general_purpose::STANDARD.encode(key.sign(&segment.digest()));
+        segment
+    }
+
+    fn stored(segment: &LogSegment) -> StoredSegment {
+        StoredSegment {
+            segment_index: segment.segment_index,
+            prev_hash: segment.prev_hash.clone(),
+            head_hash: segment.head_hash.clone(),
+            signer_key: segment.signer_key.clone(),
+        }
+    }
+
+    fn genesis() -> String {
+        agent_log::genesis_hash(BOOT, CHAIN)
+    }
+
+    #[test]
+    fn test_sealed_segment_verifies() {
+        let key = Ed25519KeyPair::generate().unwrap();
+        let segment = sealed(&key, 0, 0, &genesis(), &["agent started", "connected to core"]);
+        assert!(segment.verify().is_ok());
+    }
+
+    #[test]
+    fn test_altered_lines_and_seal_are_rejected() {
+        let key = Ed25519KeyPair::generate().unwrap();
+        let segment = sealed(&key, 0, 0, &genesis(), &["a", "b", "c"]);
+
+        let mut edited = segment.clone();
+        edited.lines[1] = "x".to_string();
+        assert!(edited.verify().is_err());
+
+        let mut reordered = segment.clone();
+        reordered.lines.swap(0, 1);
+        assert!(reordered.verify().is_err());
+
+        // Truncated with a recomputed head: the chain holds but the seal no longer does
+        let mut truncated = segment.clone();
+        truncated.lines.pop();
+        truncated.head_hash = agent_log::line_hash(&agent_log::line_hash(&genesis(), "a"), "b");
+        assert!(truncated.verify().unwrap_err().contains("signature"));
+
+        let mut resealed_by_other = segment.clone();
+        let other = Ed25519KeyPair::generate().unwrap();
+        resealed_by_other.signer_key = hex::encode(other.public_key());
+        assert!(resealed_by_other.verify().is_err());
+
+        let mut retimed = segment.clone();
+        retimed.sealed_at = "2026-03-01T13:00:00.000000Z".to_string();
+        assert!(retimed.verify().is_err());
+
+        let mut empty = segment;
+        empty.lines.clear();
+        assert!(empty.verify().is_err());
+    }
+
+    #[test]
+    fn test_continuity_genesis_linked_and_gap() {
+        let key = Ed25519KeyPair::generate().unwrap();
+        let s0 = sealed(&key, 0, 0, &genesis(), &["a", "b"]);
+        let s1 = sealed(&key, 1, 2, &s0.head_hash, &["c"]);
+        let s2 = sealed(&key, 2, 3, &s1.head_hash, &["d"]);
+
+        assert_eq!(agent_log::place(&s0, &[]).unwrap(), Placement::New(Continuity::Genesis));
+        assert_eq!(agent_log::place(&s1, &[stored(&s0)]).unwrap(), Placement::New(Continuity::Linked));
+        // s1 never arrived
+        assert_eq!(agent_log::place(&s2, &[]).unwrap(), Placement::New(Continuity::Gap));
+        // s1 arriving late still has to fit between its stored neighbours
+        assert_eq!(agent_log::place(&s1, &[stored(&s0), stored(&s2)]).unwrap(), Placement::New(Continuity::Linked));
+
+        let bad_start = sealed(&key, 0, 0, &"0".repeat(64), &["a"]);
+        assert!(agent_log::place(&bad_start, &[]).is_err());
+    }
+
+    #[test]
+    fn test_forks_key_changes_and_duplicates() {
+        let key = Ed25519KeyPair::generate().unwrap();
+        let s0 = sealed(&key, 0, 0, &genesis(), &["a"]);
+        let s1 = sealed(&key, 1, 1, &s0.head_hash, &["b"]);
+
+        assert_eq!(agent_log::place(&s1, &[stored(&s0), stored(&s1)]).unwrap(), Placement::Duplicate);
+
+        let rewritten = sealed(&key, 1, 1, &s0.head_hash, &["rewritten"]);
+        assert!(agent_log::place(&rewritten, &[stored(&s0), stored(&s1)]).is_err());
+
+        let fork = sealed(&key, 1, 1, &agent_log::line_hash(&genesis(), "other"), &["b"]);
+        assert!(agent_log::place(&fork, &[stored(&s0)]).is_err());
+
+        let other = Ed25519KeyPair::generate().unwrap();
+        let foreign = sealed(&other, 1, 1, &s0.head_hash, &["b"]);
+        assert!(foreign.verify().is_ok());
+        assert!(agent_log::place(&foreign, &[stored(&s0)]).unwrap_err().contains("signer key"));
+
+        let before_stored = sealed(&key, 0, 0, &genesis(), &["replaced"]);
+        assert!(agent_log::place(&before_stored, &[stored(&s1)]).is_err());
+    }
+
+    #[test]
+    fn test_spool_round_trip() {
+        let key = Ed25519KeyPair::generate().unwrap();
+        let dir = TempDir::new().unwrap();
+        let entry = SpoolEntry {
+            segment_id: Uuid::new_v4(),
+            agent_id: Uuid::new_v4(),
+            agent_component_id: "web-1".to_string(),
+            received_at: Utc::now(),
+            continuity: Continuity::Genesis,
+            segment: sealed(&key, 0, 0, &genesis(), &["a"]),
+        };
+        agent_log::write_spool(dir.path(), &entry).unwrap();
+        let path = dir.path().join(format!("{}.json", entry.segment_id));
+        let read: SpoolEntry = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(read, entry);
+        assert!(read.segment.verify().is_ok());
+
+        agent_log::remove_spool(dir.path(), entry.segment_id);
+        assert!(!path.exists());
+    }
+}
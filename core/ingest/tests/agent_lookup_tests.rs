@@ -0,0 +1,67 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/agent_lookup_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests that agent lookup and registration bind the agent type as a parameter - hostile agent_type strings are rejected and can never change the statements
+
+/*
+ * Agent Lookup Tests
+ *
+ * The Postgres statements are fixed text with positional parameters; the agent type is cast to
+ * event_source_type server-side. Hostile agent types are rejected before any query, and on the
+ * SQLite lab backend identities containing SQL are stored verbatim without touching other rows.
+ */
+
+#[cfg(test)]
+mod tests {
+    use ingest::storage::postgres::{validate_agent_type, AGENT_INSERT_SQL, AGENT_LOOKUP_SQL, AGENT_TYPES};
+    use ingest::storage::{SqliteStore, TelemetrySource, TelemetryStore};
+
+    const HOSTILE: &[&str] = &[
+        "linux_agent'; DROP TABLE agents; --",
+        "linux_agent' OR '1'='1",
+        "linux_agent'::event_source_type OR true --",
+        "linux_agent\0",
+        "LINUX_AGENT",
+        " linux_agent",
+        "",
+    ];
+
+    #[test]
+    fn test_statements_bind_agent_type_as_parameter() {
+        for sql in [AGENT_LOOKUP_SQL, AGENT_INSERT_SQL] {
+            assert!(sql.contains("::text::event_source_type"), "agent type must be a cast parameter: {}", sql);
+            assert!(!sql.contains('\''), "statement must not embed literals: {}", sql);
+            assert!(!sql.contains('{'), "statement must not be a format template: {}", sql);
+        }
+        assert!(AGENT_LOOKUP_SQL.contains("$2::text::event_source_type"));
+        assert!(AGENT_INSERT_SQL.contains("$2::text::event_source_type"));
+    }
+
+    #[test]
+    fn test_hostile_agent_types_are_rejected() {
+        for hostile in HOSTILE {
+            let err = validate_agent_type(hostile).unwrap_err();
+            assert!(err.contains("Invalid agent_type"), "{}", err);
+        }
+        for agent_type in AGENT_TYPES {
+            assert_eq!(validate_agent_type(agent_type).unwrap(), *agent_type);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hostile_identity_is_stored_verbatim() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let honest = store.resolve_agent("lab-host-1", TelemetrySource::LinuxAgent).await.unwrap();
+        let hostile = store
+            .resolve_agent("x' OR '1'='1'; DELETE FROM agents; --", TelemetrySource::LinuxAgent)
+            .await
+            .unwrap();
+        assert_ne!(hostile, honest);
+
+        // Both rows still resolve to their own ids
+        assert_eq!(store.resolve_agent("lab-host-1", TelemetrySource::LinuxAgent).await.unwrap(), honest);
+        assert_eq!(
+            store.resolve_agent("x' OR '1'='1'; DELETE FROM agents; --", TelemetrySource::LinuxAgent).await.unwrap(),
+            hostile
+        );
+    }
+}
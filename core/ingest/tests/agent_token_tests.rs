@@ -0,0 +1,78 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/agent_token_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for per-agent ingest bearer tokens - issuance, MAC verification, expiry, and header parsing
+
+/*
+ * Agent Token Tests
+ * 
+ * Tests that verify agent API tokens fail-closed on any tampering,
+ * expiry, malformed structure, or weak signing key.
+ */
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use uuid::Uuid;
+    use ingest::agent_token::{
+        parse_bearer, token_sha256, AgentTokenError, AgentTokenSigner,
+    };
+
+    fn signer() -> AgentTokenSigner {
+        AgentTokenSigner::new(&[7u8; 32]).unwrap()
+    }
+
+    #[test]
+    fn test_issue_verify_roundtrip() {
+        let s = signer();
+        let agent_id = Uuid::new_v4();
+        let issued = s.issue(agent_id, Utc::now() + Duration::hours(1));
+        let claims = s.verify(&issued.token, Utc::now()).unwrap();
+        assert_eq!(claims.agent_id, agent_id);
+        assert_eq!(claims.token_id, issued.claims.token_id);
+        assert_eq!(issued.token_sha256, token_sha256(&issued.token));
+    }
+
+    #[test]
+    fn test_tampered_token_rejected() {
+        let s = signer();
+        let issued = s.issue(Uuid::new_v4(), Utc::now() + Duration::hours(1));
+        let mut parts: Vec<String> = issued.token.split('.').map(String::from).collect();
+        parts[2] = Uuid::new_v4().to_string();
+        let forged = parts.join(".");
+        assert_eq!(s.verify(&forged, Utc::now()), Err(AgentTokenError::BadMac));
+    }
+
+    #[test]
+    fn test_token_from_other_key_rejected() {
+        let other = AgentTokenSigner::new(&[9u8; 32]).unwrap();
+        let issued = other.issue(Uuid::new_v4(), Utc::now() + Duration::hours(1));
+        assert_eq!(signer().verify(&issued.token, Utc::now()), Err(AgentTokenError::BadMac));
+    }
+
+    #[test]
+    fn test_expired_token_rejected() {
+        let s = signer();
+        let issued = s.issue(Uuid::new_v4(), Utc::now() - Duration::seconds(1));
+        assert!(matches!(s.verify(&issued.token, Utc::now()), Err(AgentTokenError::Expired(_))));
+    }
+
+    #[test]
+    fn test_malformed_token_rejected() {
+        let s = signer();
+        assert!(matches!(s.verify("not-a-token", Utc::now()), Err(AgentTokenError::Malformed(_))));
+        assert!(matches!(s.verify("rey0.a.b.c.d", Utc::now()), Err(AgentTokenError::Malformed(_))));
+    }
+
+    #[test]
+    fn test_short_key_rejected() {
+        assert!(matches!(AgentTokenSigner::new(&[1u8; 16]), Err(AgentTokenError::KeyInvalid(_))));
+    }
+
+    #[test]
+    fn test_parse_bearer() {
+        assert_eq!(parse_bearer(Some("Bearer abc")), Ok("abc"));
+        assert_eq!(parse_bearer(None), Err(AgentTokenError::Missing));
+        assert_eq!(parse_bearer(Some("Bearer   ")), Err(AgentTokenError::Missing));
+        assert!(matches!(parse_bearer(Some("Basic abc")), Err(AgentTokenError::Malformed(_))));
+    }
+}
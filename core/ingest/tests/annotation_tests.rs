@@ -0,0 +1,49 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/annotation_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for investigation annotations - subject id normalization per subject type and note/author validation
+
+/*
+ * Annotation Tests
+ *
+ * Incident and detection subjects are canonical UUIDs, timeline entries opaque ids without
+ * whitespace; empty or oversized notes and authors are refused before anything is written.
+ */
+
+#[cfg(test)]
+mod tests {
+    use ingest::annotation::{self, AnnotationSubject, MAX_BODY_BYTES};
+
+    #[test]
+    fn test_subject_ids_are_normalized() {
+        let id = "6F9619FF-8B86-D011-B42D-00C04FC964FF";
+        assert_eq!(
+            AnnotationSubject::Incident.normalize_id(&format!(" {} ", id)).unwrap(),
+            "6f9619ff-8b86-d011-b42d-00c04fc964ff"
+        );
+        assert_eq!(AnnotationSubject::Detection.normalize_id(id).unwrap(), "6f9619ff-8b86-d011-b42d-00c04fc964ff");
+        assert!(AnnotationSubject::Incident.normalize_id("INC-42").is_err());
+        assert!(AnnotationSubject::Detection.normalize_id("").is_err());
+
+        assert_eq!(AnnotationSubject::TimelineEntry.normalize_id("ev-0001").unwrap(), "ev-0001");
+        assert!(AnnotationSubject::TimelineEntry.normalize_id("ev 0001").is_err());
+        assert!(AnnotationSubject::TimelineEntry.normalize_id("  ").is_err());
+        assert!(AnnotationSubject::TimelineEntry.normalize_id(&"e".repeat(257)).is_err());
+
+        for subject in [AnnotationSubject::Incident, AnnotationSubject::Detection, AnnotationSubject::TimelineEntry] {
+            assert_eq!(AnnotationSubject::parse(subject.as_str()), Some(subject));
+        }
+        assert_eq!(AnnotationSubject::parse("entity"), None);
+    }
+
+    #[test]
+    fn test_notes_and_authors_are_bounded() {
+        assert!(annotation::check_body("Encryption started from the backup share").is_ok());
+        assert!(annotation::check_body(" \n ").is_err());
+        assert!(annotation::check_body(&"a".repeat(MAX_BODY_BYTES)).is_ok());
+        assert!(annotation::check_body(&"a".repeat(MAX_BODY_BYTES + 1)).is_err());
+
+        assert!(annotation::check_field("author", "analyst-1").is_ok());
+        assert!(annotation::check_field("author", "").is_err());
+        assert!(annotation::check_field("author", &"a".repeat(257)).is_err());
+    }
+}
@@ -0,0 +1,60 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/attack_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for MITRE ATT&CK tags, the technique catalog, built-in detector tags and sensor data sources
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use ingest::attack::{self, AttackTag, DataSource, BUILTIN_DETECTORS, TECHNIQUES};
+
+    #[test]
+    fn test_tags_normalize_tactic_and_validate_technique() {
+        let tag = AttackTag::new("t1053.005", "Persistence").unwrap();
+        assert_eq!((tag.technique_id.as_str(), tag.tactic.as_str()), ("T1053.005", "persistence"));
+        assert_eq!(AttackTag::new("T1486", "TA0040").unwrap().tactic, "impact");
+        assert_eq!(AttackTag::new("T1021", "lateral_movement").unwrap().tactic, "lateral-movement");
+        assert_eq!(AttackTag::new("T1071", "Command and Control").unwrap().tactic, "command-and-control");
+        for bad in ["T148", "T14860", "T1486.5", "1486", "TA0040"] {
+            assert!(AttackTag::new(bad, "impact").is_err(), "{} accepted", bad);
+        }
+        assert!(AttackTag::new("T1486", "ransom").unwrap_err().contains("unknown ATT&CK tactic"));
+        // A deserialized tag must already be in normalized form
+        assert!(tag.validate().is_ok());
+        let raw = AttackTag { technique_id: "T1486".to_string(), tactic: "Impact".to_string() };
+        assert!(raw.validate().unwrap_err().contains("not normalized"));
+    }
+
+    #[test]
+    fn test_catalog_and_detector_table_are_consistent() {
+        let mut seen = HashSet::new();
+        for t in TECHNIQUES {
+            assert!(seen.insert(t.id), "{} listed twice", t.id);
+            AttackTag::new(t.id, t.tactic).unwrap().validate().unwrap();
+            assert!(!t.data_sources.is_empty(), "{} has no data source", t.id);
+        }
+        for d in BUILTIN_DETECTORS {
+            if let Some((technique_id, _)) = d.attack {
+                assert!(attack::technique(technique_id).is_some(), "{} not in the catalog", technique_id);
+            }
+        }
+        let tag = attack::for_detector("ingest_process_lineage", "process_lineage_tampered_event").unwrap();
+        assert_eq!((tag.technique_id.as_str(), tag.tactic.as_str()), ("T1562", "defense-evasion"));
+        assert_eq!(attack::for_detector("ingest_rate_budget", "ingest_rate_budget_exceeded"), None);
+        assert_eq!(attack::for_detector("ingest_sandbox", "no_such_detector"), None);
+    }
+
+    #[test]
+    fn test_sensor_data_sources() {
+        let linux = attack::sensor_data_sources("linux_agent");
+        assert!(linux.contains(&DataSource::Filesystem) && !linux.contains(&DataSource::Registry));
+        assert_eq!(attack::sensor_data_sources("dpi_probe").into_iter().collect::<Vec<_>>(), vec![DataSource::NetworkFlow]);
+        // Windows categories follow the Windows event mapping
+        let windows = attack::sensor_data_sources("windows_agent");
+        for source in [DataSource::Process, DataSource::Registry, DataSource::Service, DataSource::ScheduledTask, DataSource::AuditLog] {
+            assert!(windows.contains(&source), "{} missing", source.as_str());
+        }
+        assert!(!windows.contains(&DataSource::NetworkFlow));
+        assert!(attack::sensor_data_sources("unknown").is_empty());
+    }
+}
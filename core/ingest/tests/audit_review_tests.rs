@@ -0,0 +1,158 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/audit_review_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for audit log review attestations - range verification and root hash, detection of altered or unlinked entries, reviewer list, range checks and reviewed/unreviewed coverage
+
+/*
+ * Audit Review Tests
+ *
+ * A range verifies only if every entry links to its predecessor and carries the chain hash of
+ * predecessor and payload; the root hash is the chain hash at the end of the range. Coverage
+ * merges overlapping attestations and reports the gaps of the requested period.
+ */
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Method;
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+    use ingest::audit_review::{
+        self, AuditReviewAttestation, AuditReviewers, ChainLink, RangeVerifier, ReviewPeriod, ReviewerAuth,
+    };
+    use ingest::operator_auth::OperatorRole;
+    use sha2::{Digest, Sha256};
+    use uuid::Uuid;
+
+    fn at(minute: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 1, 0, 0, 0).unwrap() + Duration::minutes(minute)
+    }
+
+    /// A well-formed chain of `n` entries, one a minute.
+    fn chain(n: usize) -> Vec<ChainLink> {
+        let mut links: Vec<ChainLink> = Vec::new();
+        for i in 0..n {
+            let payload_sha256 = Sha256::digest(format!("{{\"entry\":{}}}", i).as_bytes()).to_vec();
+            let prev = links.last();
+            let mut hasher = Sha256::new();
+            hasher.update(prev.map(|p| p.chain_hash_sha256.clone()).unwrap_or_else(|| vec![0u8; 32]));
+            hasher.update(&payload_sha256);
+            links.push(ChainLink {
+                audit_id: Uuid::new_v4(),
+                created_at: at(i as i64),
+                prev_audit_id: prev.map(|p| p.audit_id),
+                payload_sha256,
+                chain_hash_sha256: hasher.finalize().to_vec(),
+            });
+        }
+        links
+    }
+
+    fn verify(predecessor: Option<&ChainLink>, links: &[ChainLink]) -> Result<audit_review::RangeDigest, String> {
+        let mut verifier = RangeVerifier::new(predecessor);
+        for link in links {
+            verifier.push(link)?;
+        }
+        Ok(verifier.finish())
+    }
+
+    #[test]
+    fn test_range_verifies_with_root_hash_of_last_entry() {
+        let links = chain(6);
+        let digest = verify(Some(&links[1]), &links[2..5]).unwrap();
+        assert_eq!(digest.entry_count, 3);
+        assert_eq!(digest.first_audit_id, Some(links[2].audit_id));
+        assert_eq!(digest.last_audit_id, Some(links[4].audit_id));
+        assert_eq!(digest.root_hash, hex::encode(&links[4].chain_hash_sha256));
+
+        // From the start of the log
+        assert_eq!(verify(None, &links).unwrap().root_hash, hex::encode(&links[5].chain_hash_sha256));
+
+        // Empty range: the chain head before it
+        let empty = verify(Some(&links[3]), &[]).unwrap();
+        assert_eq!(empty.entry_count, 0);
+        assert_eq!((empty.first_audit_id, empty.last_audit_id), (None, None));
+        assert_eq!(empty.root_hash, hex::encode(&links[3].chain_hash_sha256));
+        assert_eq!(verify(None, &[]).unwrap().root_hash, "0".repeat(64));
+    }
+
+    #[test]
+    fn test_altered_or_unlinked_entries_break_the_range() {
+        let links = chain(5);
+
+        let mut altered = links.clone();
+        altered[2].payload_sha256 = Sha256::digest(b"rewritten").to_vec();
+        assert!(verify(Some(&altered[0]), &altered[1..]).unwrap_err().contains(&altered[2].audit_id.to_string()));
+
+        // An entry removed from the middle leaves its successor pointing at it
+        let mut removed = links.clone();
+        removed.remove(2);
+        assert!(verify(Some(&removed[0]), &removed[1..]).is_err());
+
+        // The range must continue the entry before it
+        assert!(verify(Some(&links[0]), &links[2..]).is_err());
+        assert!(verify(None, &links[1..]).is_err());
+    }
+
+    #[test]
+    fn test_reviewers_and_range_checks() {
+        let reviewers = AuditReviewers::parse(" alice@corp , auditor-2,").unwrap();
+        assert!(reviewers.contains("alice@corp"));
+        assert!(reviewers.contains("auditor-2"));
+        assert!(!reviewers.contains("mallory"));
+        assert!(AuditReviewers::parse("").unwrap().is_empty());
+        assert!(AuditReviewers::parse(&"r".repeat(257)).is_err());
+
+        let now = at(120);
+        assert!(audit_review::check_range(at(0), at(60), now).is_ok());
+        assert!(audit_review::check_range(at(60), at(60), now).is_err());
+        assert!(audit_review::check_range(at(61), at(60), now).is_err());
+        // Not yet settled
+        assert!(audit_review::check_range(at(0), now - Duration::seconds(30), now).is_err());
+    }
+
+    #[test]
+    fn test_any_role_may_attempt_an_attestation() {
+        for role in [OperatorRole::Viewer, OperatorRole::Analyst, OperatorRole::Admin] {
+            assert!(role.permits(&Method::POST, "/admin/audit-reviews"));
+            assert!(role.permits(&Method::GET, "/admin/audit-reviews/coverage"));
+        }
+        assert!(!OperatorRole::Viewer.permits(&Method::POST, "/admin/audit-reviews/coverage"));
+    }
+
+    fn attestation(start: i64, end: i64, attested: i64) -> AuditReviewAttestation {
+        AuditReviewAttestation {
+            attestation_id: Uuid::new_v4(),
+            attested_at: at(attested),
+            reviewer: "alice@corp".to_string(),
+            authenticated_by: ReviewerAuth::Oidc,
+            range_start: at(start),
+            range_end: at(end),
+            entry_count: 1,
+            first_audit_id: None,
+            last_audit_id: None,
+            root_hash: "0".repeat(64),
+            notes: None,
+        }
+    }
+
+    #[test]
+    fn test_coverage_merges_attestations_and_reports_gaps() {
+        let report = audit_review::coverage(
+            at(0),
+            at(100),
+            vec![attestation(50, 70, 3), attestation(10, 30, 1), attestation(25, 40, 2), attestation(200, 300, 4)],
+        );
+        let period = |s, e| ReviewPeriod { start: at(s), end: at(e) };
+        assert_eq!(report.reviewed, vec![period(10, 40), period(50, 70)]);
+        assert_eq!(report.unreviewed, vec![period(0, 10), period(40, 50), period(70, 100)]);
+        // Attestations outside the period are not part of the report, the rest are oldest first
+        let order: Vec<_> = report.attestations.iter().map(|a| a.attested_at).collect();
+        assert_eq!(order, vec![at(1), at(2), at(3)]);
+
+        // Attestations reaching past the period are clipped to it
+        let full = audit_review::coverage(at(20), at(30), vec![attestation(0, 100, 1)]);
+        assert_eq!(full.reviewed, vec![period(20, 30)]);
+        assert!(full.unreviewed.is_empty());
+
+        let none = audit_review::coverage(at(0), at(10), Vec::new());
+        assert_eq!(none.unreviewed, vec![period(0, 10)]);
+    }
+}
@@ -0,0 +1,100 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/component_budget_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for policy-driven per-component ingest rate budgets - fixed windows, one violation per window, quota replacement
+
+/*
+ * Component Budget Tests
+ *
+ * Budgets apply per component instance of a quota'd type, reset with each one-minute window,
+ * and report only the first rejection of a window as the violation to record.
+ */
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use chrono::Utc;
+
+    use ingest::component_budget::{BudgetDecision, ComponentQuota, ComponentRateBudget, BUDGET_WINDOW};
+
+    fn quota(component_type: &str, events_per_minute: u64) -> ComponentQuota {
+        ComponentQuota {
+            component_type: component_type.to_string(),
+            events_per_minute,
+            policy_id: "ingest-budgets".to_string(),
+            policy_version: "1.0.0".to_string(),
+        }
+    }
+
+    fn first_in_window(decision: BudgetDecision) -> Option<bool> {
+        match decision {
+            BudgetDecision::Allowed => None,
+            BudgetDecision::Exceeded(v) => Some(v.first_in_window),
+        }
+    }
+
+    #[test]
+    fn test_unquoted_component_type_is_not_budgeted() {
+        let budget = ComponentRateBudget::new(vec![quota("linux_agent", 1)]);
+        for _ in 0..10 {
+            assert!(matches!(budget.check("dpi_probe", "probe-1"), BudgetDecision::Allowed));
+        }
+    }
+
+    #[test]
+    fn test_one_violation_per_window_per_component() {
+        let budget = ComponentRateBudget::new(vec![quota("linux_agent", 2)]);
+        let t0 = Instant::now();
+        let wall = Utc::now();
+
+        assert_eq!(first_in_window(budget.check_at("linux_agent", "host-a", t0, wall)), None);
+        assert_eq!(first_in_window(budget.check_at("linux_agent", "host-a", t0, wall)), None);
+        assert_eq!(first_in_window(budget.check_at("linux_agent", "host-a", t0, wall)), Some(true));
+        assert_eq!(first_in_window(budget.check_at("linux_agent", "host-a", t0, wall)), Some(false));
+
+        // Separate instance, separate budget
+        assert_eq!(first_in_window(budget.check_at("linux_agent", "host-b", t0, wall)), None);
+
+        let stats = budget.stats();
+        assert_eq!(stats.rejected_events, 2);
+        assert_eq!(stats.violation_windows, 1);
+    }
+
+    #[test]
+    fn test_window_resets_after_one_minute() {
+        let budget = ComponentRateBudget::new(vec![quota("linux_agent", 1)]);
+        let t0 = Instant::now();
+        let wall = Utc::now();
+
+        assert_eq!(first_in_window(budget.check_at("linux_agent", "host-a", t0, wall)), None);
+        assert_eq!(first_in_window(budget.check_at("linux_agent", "host-a", t0 + Duration::from_secs(59), wall)), Some(true));
+
+        let next = t0 + BUDGET_WINDOW;
+        let next_wall = wall + chrono::Duration::seconds(60);
+        assert_eq!(first_in_window(budget.check_at("linux_agent", "host-a", next, next_wall)), None);
+        match budget.check_at("linux_agent", "host-a", next, next_wall) {
+            BudgetDecision::Exceeded(v) => {
+                assert!(v.first_in_window);
+                assert_eq!(v.window_start, next_wall);
+                assert_eq!(v.quota.policy_id, "ingest-budgets");
+            }
+            BudgetDecision::Allowed => panic!("second event in a 1/min window must be rejected"),
+        }
+    }
+
+    #[test]
+    fn test_replace_quotas_applies_and_removes_budgets() {
+        let budget = ComponentRateBudget::new(vec![quota("linux_agent", 1)]);
+        assert!(matches!(budget.check("linux_agent", "host-a"), BudgetDecision::Allowed));
+        assert!(matches!(budget.check("linux_agent", "host-a"), BudgetDecision::Exceeded(_)));
+
+        budget.replace_quotas(vec![quota("linux_agent", 5), quota("dpi_probe", 1)]);
+        assert_eq!(budget.quotas().len(), 2);
+        assert!(matches!(budget.check("linux_agent", "host-a"), BudgetDecision::Allowed));
+
+        budget.replace_quotas(Vec::new());
+        assert!(budget.quotas().is_empty());
+        assert!(matches!(budget.check("linux_agent", "host-a"), BudgetDecision::Allowed));
+        assert_eq!(budget.stats().tracked_components, 0);
+    }
+}
@@ -0,0 +1,189 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/dashboard_cache_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for the dashboard cache - the aggregate queries on the SQLite lab backend, hits within the TTL, invalidation on writes, concurrent loads and the hit/miss counters
+
+/*
+ * Dashboard Cache Tests
+ *
+ * The SQLite lab backend answers the three dashboard aggregates; the cache serves them from
+ * memory within the TTL, re-reads after expiry or an invalidation (a committed detection shows
+ * up at once), loads once for concurrent requests and counts hits, misses and invalidations.
+ */
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use chrono::Utc;
+    use serde_json::json;
+    use sha2::{Digest, Sha256};
+    use uuid::Uuid;
+
+    use ingest::dashboard_cache::DashboardCache;
+    use ingest::storage::{
+        AgentTypeCount, DetectionRecord, PayloadStorage, RawEventRecord, SeverityCount, SqliteStore, TelemetrySource,
+        TelemetryStore,
+    };
+
+    const TTL: Duration = Duration::from_secs(15);
+
+    async fn add_detection(store: &SqliteStore, severity: &str, key: &str) {
+        let detection = DetectionRecord {
+            detection_engine: "ingest_key_pinning".to_string(),
+            detection_name: "agent_unexpected_signing_key".to_string(),
+            detection_category: Some("identity_spoofing".to_string()),
+            severity: severity.to_string(),
+            confidence: 1.0,
+            reasoning: "test".to_string(),
+            artifacts: json!({ "key": key }),
+            deterministic_key: Sha256::digest(key.as_bytes()).to_vec(),
+        };
+        let mut tx = store.begin().await.unwrap();
+        tx.insert_detection(&detection).await.unwrap();
+        tx.commit().await.unwrap();
+    }
+
+    async fn add_raw_event(store: &SqliteStore, agent_id: Uuid) {
+        let payload = json!({ "event": "test", "id": Uuid::new_v4().to_string() });
+        let raw = RawEventRecord {
+            source: TelemetrySource::LinuxAgent,
+            agent_id,
+            observed_at: Utc::now(),
+            event_name: "process_start".to_string(),
+            payload_sha256: Sha256::digest(payload.to_string().as_bytes()).to_vec(),
+            payload_json: serde_json::value::to_raw_value(&payload).unwrap(),
+            payload_storage: PayloadStorage::Full,
+            payload_storage_reason: "policy:full".to_string(),
+            residency_region: None,
+            zone: None,
+        };
+        let mut tx = store.begin().await.unwrap();
+        tx.insert_raw(&raw).await.unwrap();
+        tx.commit().await.unwrap();
+    }
+
+    fn severity(severity: &str, detections: u64) -> SeverityCount {
+        SeverityCount { severity: severity.to_string(), detections }
+    }
+
+    #[tokio::test]
+    async fn test_aggregates_on_lab_backend() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        add_detection(&store, "warning", "a").await;
+        add_detection(&store, "critical", "b").await;
+        add_detection(&store, "warning", "c").await;
+        let since = Utc::now() - chrono::Duration::hours(1);
+        assert_eq!(
+            store.detection_severity_counts(since).await.unwrap(),
+            vec![severity("critical", 1), severity("warning", 2)]
+        );
+        assert!(store.detection_severity_counts(Utc::now() + chrono::Duration::hours(1)).await.unwrap().is_empty());
+
+        let agent_id = store.resolve_agent("host-a", TelemetrySource::LinuxAgent).await.unwrap();
+        store.resolve_agent("probe-a", TelemetrySource::DpiProbe).await.unwrap();
+        add_raw_event(&store, agent_id).await;
+        add_raw_event(&store, agent_id).await;
+        let rate = store.event_rate(since).await.unwrap();
+        assert_eq!(rate.iter().map(|m| m.events).sum::<u64>(), 2);
+        assert!(rate.iter().all(|m| m.minute.timestamp() % 60 == 0));
+
+        let fleet = store.fleet_summary(since).await.unwrap();
+        assert_eq!((fleet.agents, fleet.decommissioned, fleet.reporting), (2, 0, 2));
+        assert_eq!(
+            fleet.by_type[0],
+            AgentTypeCount { agent_type: "dpi_probe".to_string(), agents: 1, reporting: 1 }
+        );
+        assert_eq!(store.fleet_summary(Utc::now() + chrono::Duration::hours(1)).await.unwrap().reporting, 0);
+    }
+
+    #[tokio::test]
+    async fn test_hits_within_ttl_and_reloads_after_expiry() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let cache = DashboardCache::new(TTL);
+        let t0 = Instant::now();
+        add_detection(&store, "critical", "a").await;
+
+        let first = cache.detections_by_severity_at(&store, t0).await.unwrap();
+        assert_eq!(first.severities, vec![severity("critical", 1)]);
+
+        // Not invalidated: the cached counts are served until the TTL expires
+        add_detection(&store, "critical", "b").await;
+        let cached = cache.detections_by_severity_at(&store, t0 + TTL - Duration::from_secs(1)).await.unwrap();
+        assert_eq!(cached, first);
+        let expired = cache.detections_by_severity_at(&store, t0 + TTL).await.unwrap();
+        assert_eq!(expired.severities, vec![severity("critical", 2)]);
+
+        let stats = cache.stats();
+        assert_eq!(stats.ttl_secs, 15);
+        let detections = stats.queries.iter().find(|q| q.query == "detections_by_severity").unwrap();
+        assert_eq!((detections.hits, detections.misses, detections.invalidations), (1, 2, 0));
+        assert!(detections.age_secs.is_some());
+        assert_eq!(stats.queries.iter().find(|q| q.query == "fleet_summary").unwrap().age_secs, None);
+    }
+
+    #[tokio::test]
+    async fn test_invalidation_reloads_at_once() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let cache = DashboardCache::new(TTL);
+        let t0 = Instant::now();
+
+        assert!(cache.detections_by_severity_at(&store, t0).await.unwrap().severities.is_empty());
+        add_detection(&store, "error", "a").await;
+        cache.invalidate_detections();
+        let after = cache.detections_by_severity_at(&store, t0).await.unwrap();
+        assert_eq!(after.severities, vec![severity("error", 1)]);
+
+        assert_eq!(cache.fleet_summary_at(&store, t0).await.unwrap().fleet.agents, 0);
+        store.resolve_agent("host-a", TelemetrySource::LinuxAgent).await.unwrap();
+        cache.invalidate_fleet();
+        assert_eq!(cache.fleet_summary_at(&store, t0).await.unwrap().fleet.agents, 1);
+
+        // Invalidating one aggregate leaves the others cached
+        let events = cache.events_per_minute_at(&store, t0).await.unwrap();
+        cache.invalidate_detections();
+        assert_eq!(cache.events_per_minute_at(&store, t0).await.unwrap(), events);
+
+        let stats = cache.stats();
+        let detections = stats.queries.iter().find(|q| q.query == "detections_by_severity").unwrap();
+        assert_eq!((detections.misses, detections.invalidations), (2, 2));
+        assert_eq!(detections.age_secs, None);
+        let events = stats.queries.iter().find(|q| q.query == "events_per_minute").unwrap();
+        assert_eq!((events.hits, events.misses, events.invalidations), (1, 1, 0));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_requests_load_once() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let cache = DashboardCache::new(TTL);
+        let t0 = Instant::now();
+
+        // Polled together on one task: the first takes the load, the rest are served its result
+        let (a, b, c, d) = tokio::join!(
+            cache.fleet_summary_at(&store, t0),
+            cache.fleet_summary_at(&store, t0),
+            cache.fleet_summary_at(&store, t0),
+            cache.fleet_summary_at(&store, t0),
+        );
+        for result in [a, b, c, d] {
+            assert_eq!(result.unwrap().fleet.agents, 0);
+        }
+        let stats = cache.stats();
+        let fleet = stats.queries.iter().find(|q| q.query == "fleet_summary").unwrap();
+        assert_eq!((fleet.hits, fleet.misses), (3, 1));
+    }
+
+    #[tokio::test]
+    async fn test_zero_ttl_disables_caching() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let cache = DashboardCache::new(Duration::ZERO);
+        let t0 = Instant::now();
+
+        assert!(cache.detections_by_severity_at(&store, t0).await.unwrap().severities.is_empty());
+        add_detection(&store, "warning", "a").await;
+        assert_eq!(cache.detections_by_severity_at(&store, t0).await.unwrap().severities, vec![severity("warning", 1)]);
+
+        let stats = cache.stats();
+        let detections = stats.queries.iter().find(|q| q.query == "detections_by_severity").unwrap();
+        assert_eq!((detections.hits, detections.misses, detections.age_secs), (0, 2, None));
+    }
+}
@@ -0,0 +1,119 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/disk_quota_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for the evidence spool disk quotas - configuration against enabled spools, upload admission with 507 and Retry-After, purge_oldest admission and the disk_quota health subsystem
+
+/*
+ * Disk Quota Tests
+ *
+ * Tests that a quota on a spool that is off is refused at startup, that a reject spool answers
+ * 507 with Retry-After once an upload would exceed its budget while a purge_oldest spool keeps
+ * accepting, and that the health report degrades past the warning level and turns unhealthy at
+ * the budget.
+ */
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use axum::body::Body;
+    use axum::extract::State;
+    use axum::http::{header, Request, StatusCode};
+    use axum::routing::post;
+    use axum::{middleware, Router};
+    use governor::quota;
+    use health::{HealthReport, HealthStatus};
+    use tower::Service;
+
+    use ingest::disk_quota::{self, DiskQuotaConfig, DiskQuotas};
+
+    const MIB: u64 = 1024 * 1024;
+
+    fn config(spec: &str) -> DiskQuotaConfig {
+        DiskQuotaConfig {
+            quotas: quota::parse_specs(spec).unwrap(),
+            warn_percent: 80,
+            scan_interval: Duration::from_secs(30),
+        }
+    }
+
+    fn quotas(spec: &str) -> DiskQuotas {
+        DiskQuotas::new(
+            &config(spec),
+            &[
+                (disk_quota::PCAP_SPOOL, Some(Path::new("/nonexistent/pcap"))),
+                (disk_quota::MEMORY_SPOOL, Some(Path::new("/nonexistent/memory"))),
+                (disk_quota::SANDBOX_SPOOL, None),
+                (disk_quota::AGENT_LOG_SPOOL, None),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_quota_on_disabled_spool_fails_closed() {
+        let spools = [(disk_quota::PCAP_SPOOL, Some(Path::new("/nonexistent/pcap"))), (disk_quota::SANDBOX_SPOOL, None)];
+        let err = DiskQuotas::new(&config("sandbox_spool:10"), &spools).err().unwrap();
+        assert!(err.contains("FAIL-CLOSED"), "{}", err);
+        assert!(DiskQuotas::new(&config("pcap_spool:10:purge_oldest"), &spools).is_ok());
+    }
+
+    #[test]
+    fn test_reject_and_purge_oldest_admission() {
+        let quotas = quotas("pcap_spool:1:reject,memory_spool:1:purge_oldest");
+        quotas.record_write(disk_quota::PCAP_SPOOL, MIB - 10);
+        assert_eq!(quotas.admit(disk_quota::PCAP_SPOOL, 10), Ok(()));
+        assert_eq!(quotas.admit(disk_quota::PCAP_SPOOL, 11), Err(StatusCode::INSUFFICIENT_STORAGE));
+
+        // purge_oldest keeps accepting; the next scan purges
+        quotas.record_write(disk_quota::MEMORY_SPOOL, 2 * MIB);
+        assert_eq!(quotas.admit(disk_quota::MEMORY_SPOOL, MIB), Ok(()));
+        assert_eq!(quotas.manager().purge_due().len(), 1);
+
+        // Spools without a quota are not limited
+        assert_eq!(quotas.admit(disk_quota::AGENT_LOG_SPOOL, u64::MAX), Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_insufficient_storage_carries_retry_after() {
+        let quotas = Arc::new(quotas("pcap_spool:1"));
+        quotas.record_write(disk_quota::PCAP_SPOOL, MIB);
+        let mut app = Router::new()
+            .route(
+                "/upload",
+                post(|State(q): State<Arc<DiskQuotas>>| async move {
+                    q.admit(disk_quota::PCAP_SPOOL, 1).map(|_| StatusCode::OK).unwrap_or_else(|code| code)
+                }),
+            )
+            .route_layer(middleware::from_fn_with_state(quotas.clone(), disk_quota::retry_after))
+            .with_state(quotas);
+
+        let full = app.call(Request::post("/upload").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(full.status(), StatusCode::INSUFFICIENT_STORAGE);
+        assert_eq!(full.headers()[header::RETRY_AFTER], "30");
+    }
+
+    #[test]
+    fn test_health_subsystem() {
+        let mut report = HealthReport::new("ingest", "host-a");
+        DiskQuotas::disabled().push_health(&mut report);
+        assert_eq!(report.subsystem("disk_quota").unwrap().status, HealthStatus::Healthy);
+
+        let quotas = quotas("pcap_spool:10:purge_oldest,memory_spool:10");
+        quotas.record_write(disk_quota::PCAP_SPOOL, 9 * MIB);
+        let mut report = HealthReport::new("ingest", "host-a");
+        quotas.push_health(&mut report);
+        let subsystem = report.subsystem("disk_quota").unwrap();
+        assert_eq!(subsystem.status, HealthStatus::Degraded);
+        assert_eq!(subsystem.detail.as_deref(), Some("pcap_spool warning (90% of 10 MiB, purge_oldest)"));
+        assert_eq!(report.metrics["disk_quota_pcap_spool_used_bytes"], (9 * MIB) as f64);
+
+        quotas.record_write(disk_quota::MEMORY_SPOOL, 10 * MIB);
+        let _ = quotas.admit(disk_quota::MEMORY_SPOOL, 1);
+        let mut report = HealthReport::new("ingest", "host-a");
+        quotas.push_health(&mut report);
+        assert_eq!(report.subsystem("disk_quota").unwrap().status, HealthStatus::Unhealthy);
+        assert_eq!(report.metrics["disk_quota_memory_spool_rejected"], 1.0);
+    }
+}
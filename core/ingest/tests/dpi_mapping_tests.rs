@@ -0,0 +1,78 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/dpi_mapping_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for the declarative DPI envelope.data -> dpi_probe_telemetry column mapping
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use ingest::protocol::dpi_mapping::{DpiFieldMapping, DpiFieldMappings};
+
+    #[test]
+    fn test_builtin_v1_maps_current_probe_envelope() {
+        let mappings = DpiFieldMappings::builtin().unwrap();
+        assert_eq!(mappings.versions(), vec![1]);
+        // Absent schema_version selects v1; unmapped versions have no mapping
+        let mapping = mappings.for_version(None).unwrap();
+        assert!(mappings.for_version(Some(2)).is_none());
+
+        let fields = mapping.extract(&json!({
+            "src_ip": "10.0.0.5", "dst_ip": "2001:db8::1", "src_port": 51234, "dst_port": 443,
+            "protocol": "TCP", "packet_size": 1500, "is_fragment": false,
+            "features": { "flow_duration": 120, "flow_packet_count": 42, "flow_byte_count": 61000 }
+        }));
+        assert_eq!(fields.src_ip.as_deref(), Some("10.0.0.5"));
+        assert_eq!(fields.dst_ip.as_deref(), Some("2001:db8::1"));
+        assert_eq!((fields.src_port, fields.dst_port), (Some(51234), Some(443)));
+        assert_eq!(fields.protocol.as_deref(), Some("TCP"));
+        assert_eq!((fields.packet_count, fields.byte_count), (Some(42), Some(61000)));
+        assert_eq!(fields.tls_sni, None);
+        let metadata = fields.metadata.unwrap();
+        assert_eq!(metadata.get("flow_duration"), Some(&json!(120)));
+        assert_eq!(metadata.get("is_fragment"), Some(&json!(false)));
+        assert!(fields.rejected.is_empty());
+    }
+
+    #[test]
+    fn test_tls_and_dns_fields_flow_to_columns_and_metadata() {
+        let mappings = DpiFieldMappings::builtin().unwrap();
+        let fields = mappings.for_version(Some(1)).unwrap().extract(&json!({
+            "src_ip": "10.0.0.5", "dst_ip": "not-an-ip", "dst_port": 70000,
+            "tls": { "sni": "login.example.com", "ja3": "769,47-53,0-10,23,0", "ja3s": null },
+            "ja3s": "771,49199,65281",
+            "dns": { "query": "evil.example", "qtype": "TXT", "answers": ["1.2.3.4"] }
+        }));
+        assert_eq!(fields.tls_sni.as_deref(), Some("login.example.com"));
+        assert_eq!(fields.ja3.as_deref(), Some("769,47-53,0-10,23,0"));
+        // null at the first path falls through to the next one
+        assert_eq!(fields.ja3s.as_deref(), Some("771,49199,65281"));
+        let metadata = fields.metadata.unwrap();
+        assert_eq!(metadata.get("dns_query"), Some(&json!("evil.example")));
+        assert_eq!(metadata.get("dns_answers"), Some(&json!(["1.2.3.4"])));
+        // Values that do not fit the column stay NULL and are reported
+        assert_eq!((fields.dst_ip, fields.dst_port), (None, None));
+        assert_eq!(fields.rejected, vec!["dst_ip", "dst_port"]);
+    }
+
+    #[test]
+    fn test_invalid_mappings_rejected() {
+        let unknown = r#"{ "schema_version": 2, "fields": [ { "target": "no_such_column", "paths": ["x"] } ] }"#;
+        assert!(DpiFieldMapping::from_json(unknown).unwrap_err().contains("no_such_column"));
+
+        let twice = r#"{ "schema_version": 2, "fields": [
+            { "target": "tls_sni", "paths": ["sni"] }, { "target": "tls_sni", "paths": ["tls.sni"] } ] }"#;
+        assert!(DpiFieldMapping::from_json(twice).unwrap_err().contains("mapped twice"));
+
+        let bad_path = r#"{ "schema_version": 2, "fields": [ { "target": "ja3", "paths": ["tls..ja3"] } ] }"#;
+        assert!(DpiFieldMapping::from_json(bad_path).is_err());
+
+        let bad_key = r#"{ "schema_version": 2, "fields": [ { "target": "metadata.dns-query", "paths": ["q"] } ] }"#;
+        assert!(DpiFieldMapping::from_json(bad_key).is_err());
+
+        let v2 = r#"{ "schema_version": 2, "fields": [ { "target": "metadata.quic_version", "paths": ["quic.version"] } ] }"#;
+        let mapping = DpiFieldMapping::from_json(v2).unwrap();
+        assert_eq!(mapping.schema_version, 2);
+        let fields = mapping.extract(&json!({ "quic": { "version": "v1" } }));
+        assert_eq!(fields.metadata.unwrap().get("quic_version"), Some(&json!("v1")));
+    }
+}
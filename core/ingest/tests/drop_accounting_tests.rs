@@ -0,0 +1,117 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/drop_accounting_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for dropped-event accounting - agent counters add only their increase per run, stale or replayed reports add nothing, ingest refusals are flushed per agent and day
+
+/*
+ * Drop Accounting Tests
+ *
+ * Run against the SQLite lab backend: cumulative agent counters advance per (agent, run, reason),
+ * a new run starts from zero, a report older than the last one adds nothing, and ingest-side
+ * refusals land in the same daily summary under origin ingest.
+ */
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use axum::http::StatusCode;
+    use chrono::{TimeZone, Utc};
+    use uuid::Uuid;
+
+    use ingest::drop_accounting::{self, IngestDropLedger};
+    use ingest::protocol::signed_event::LinuxAgentStats;
+    use ingest::storage::{DropOrigin, SqliteStore, TelemetrySource, TelemetryStore};
+
+    fn report(run_id: &str, counters: &[(&str, u64)]) -> LinuxAgentStats {
+        LinuxAgentStats {
+            run_id: Some(run_id.to_string()),
+            dropped_by_reason: Some(counters.iter().map(|(r, n)| (r.to_string(), *n)).collect::<BTreeMap<_, _>>()),
+        }
+    }
+
+    async fn apply(store: &SqliteStore, agent_id: Uuid, stats: &LinuxAgentStats) -> u64 {
+        let at = Utc.with_ymd_and_hms(2026, 3, 14, 12, 0, 0).unwrap();
+        let mut tx = store.begin().await.unwrap();
+        let added = drop_accounting::apply_agent_report(tx.as_mut(), agent_id, at, stats).await.unwrap();
+        tx.commit().await.unwrap();
+        added
+    }
+
+    async fn total(store: &SqliteStore, origin: DropOrigin, reason: &str) -> u64 {
+        let since = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap().date_naive();
+        store
+            .drop_summary(since, 100)
+            .await
+            .unwrap()
+            .iter()
+            .filter(|d| d.origin == origin && d.reason == reason)
+            .map(|d| d.dropped)
+            .sum()
+    }
+
+    #[tokio::test]
+    async fn test_agent_counters_add_only_their_increase_per_run() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let agent_id = store.resolve_agent("lab-host-1", TelemetrySource::LinuxAgent).await.unwrap();
+
+        assert_eq!(apply(&store, agent_id, &report("run-a", &[("queue_full", 5)])).await, 5);
+        assert_eq!(apply(&store, agent_id, &report("run-a", &[("queue_full", 8)])).await, 3);
+        // Replayed older report of the same run
+        assert_eq!(apply(&store, agent_id, &report("run-a", &[("queue_full", 5)])).await, 0);
+        // Agent restarted: its counters start from zero again
+        assert_eq!(apply(&store, agent_id, &report("run-b", &[("queue_full", 3), ("rate_limited", 2)])).await, 5);
+
+        assert_eq!(total(&store, DropOrigin::Agent, "queue_full").await, 11);
+        assert_eq!(total(&store, DropOrigin::Agent, "rate_limited").await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_malformed_agent_counters_are_skipped() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let agent_id = store.resolve_agent("lab-host-1", TelemetrySource::LinuxAgent).await.unwrap();
+
+        assert_eq!(apply(&store, agent_id, &report("run-a", &[("Queue Full", 4), ("backpressure", 1)])).await, 1);
+        assert_eq!(apply(&store, agent_id, &report("", &[("backpressure", 9)])).await, 0);
+        assert_eq!(apply(&store, agent_id, &LinuxAgentStats::default()).await, 0);
+        assert_eq!(total(&store, DropOrigin::Agent, "backpressure").await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_ingest_refusals_are_flushed_per_agent() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let agent_id = store.resolve_agent("lab-host-1", TelemetrySource::LinuxAgent).await.unwrap();
+        let ledger = IngestDropLedger::new();
+        let now = Utc::now();
+
+        ledger.record(Some(agent_id), "rate_limited", now);
+        ledger.record(Some(agent_id), "rate_limited", now);
+        ledger.record(None, "invalid", now);
+        assert_eq!(ledger.stats().pending_flush, 2);
+        assert_eq!(ledger.stats().unattributed, 1);
+
+        assert_eq!(ledger.flush(&store).await.unwrap(), 2);
+        assert_eq!(ledger.flush(&store).await.unwrap(), 0);
+        assert_eq!(ledger.stats().pending_flush, 0);
+        assert_eq!(ledger.stats().by_reason.get("rate_limited"), Some(&2));
+        assert_eq!(total(&store, DropOrigin::Ingest, "rate_limited").await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_failed_flush_keeps_counts() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let ledger = IngestDropLedger::new();
+        // Unknown agent: the foreign key fails the unit of work
+        ledger.record(Some(Uuid::new_v4()), "server_error", Utc::now());
+
+        assert!(ledger.flush(&store).await.is_err());
+        assert_eq!(ledger.stats().pending_flush, 1);
+    }
+
+    #[test]
+    fn test_only_refused_responses_count_as_drops() {
+        assert_eq!(drop_accounting::ingest_drop_reason(StatusCode::OK), None);
+        assert_eq!(drop_accounting::ingest_drop_reason(StatusCode::TOO_MANY_REQUESTS), Some("rate_limited"));
+        assert_eq!(drop_accounting::ingest_drop_reason(StatusCode::PAYLOAD_TOO_LARGE), Some("too_large"));
+        assert_eq!(drop_accounting::ingest_drop_reason(StatusCode::SERVICE_UNAVAILABLE), Some("server_error"));
+    }
+}
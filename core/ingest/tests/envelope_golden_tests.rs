@@ -0,0 +1,88 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/envelope_golden_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Golden envelope tests - every schema version's golden SignedEvents parse through the ingest views with the stored envelope hash and extracted fields pinned by the shared fixtures
+
+/*
+ * Envelope Golden Tests
+ *
+ * The samples come from qa/envelope_fixtures, which the Linux agent tests also run against. Here
+ * each sample is posted as the agent posts it and parsed the way /ingest/linux parses it: the
+ * received envelope bytes must hash to the pinned envelope_sha256, and the typed views must
+ * extract the pinned column values. A renamed, retyped or dropped field on either side fails here
+ * or in the agent's envelope tests.
+ */
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+    use serde_json::{json, Value};
+    use sha2::{Digest, Sha256};
+
+    use envelope_fixtures::{Golden, SCHEMA_VERSIONS};
+    use ingest::protocol::signed_event::{LinuxEventData, SignedEventRef};
+
+    /// Column values /ingest/linux derives from a signed event, keyed as in expected.json
+    fn extract_linux(golden: &Golden, body: &[u8]) -> Value {
+        let payload = SignedEventRef::parse(body).unwrap_or_else(|e| panic!("{}: body rejected: {}", golden.name, e));
+        let envelope = payload.envelope().unwrap_or_else(|e| panic!("{}: envelope rejected: {}", golden.name, e));
+        let data: LinuxEventData = envelope.data().unwrap_or_else(|e| panic!("{}: data rejected: {}", golden.name, e));
+        let port = |p: Option<u64>| p.filter(|p| (1..=65535).contains(p));
+        json!({
+            "payload_hash": payload.payload_hash,
+            "envelope_sha256": hex::encode(Sha256::digest(payload.envelope.get().as_bytes())),
+            "event_id": envelope.event_id,
+            "observed_at": envelope.timestamp,
+            "component_id": envelope.component_id,
+            "schema_version": envelope.schema_version.unwrap_or(1),
+            "event_category": data.event_category,
+            "event_type": data.event_type(),
+            "pid": data.pid,
+            "uid": data.uid,
+            "process_name": data.process_data.as_ref().and_then(|p| p.executable.clone()),
+            "cmdline": data.process_data.as_ref().and_then(|p| p.command_line.clone()),
+            "file_path": data.filesystem_data.as_ref().and_then(|f| f.path.clone()),
+            "network_src_ip": data.network_data.as_ref().and_then(|n| n.remote_addr.clone()),
+            "network_dst_ip": data.network_data.as_ref().and_then(|n| n.local_addr.clone()),
+            "network_src_port": data.network_data.as_ref().and_then(|n| port(n.remote_port)),
+            "network_dst_port": data.network_data.as_ref().and_then(|n| port(n.local_port)),
+            "lineage_chain_id": data.process_data.as_ref().and_then(|p| p.lineage.as_ref()).map(|l| l.chain_id.clone()),
+            "run_id": data.agent_stats.as_ref().and_then(|s| s.run_id.clone()),
+            "dropped_by_reason": data.agent_stats.as_ref().and_then(|s| s.dropped_by_reason.clone()),
+        })
+    }
+
+    #[test]
+    fn test_golden_linux_envelopes_extract_pinned_fields() {
+        for golden in envelope_fixtures::for_producer("linux_agent") {
+            let extracted = extract_linux(golden, &golden.signed_event_body());
+            assert_eq!(extracted, golden.expected(), "{} (v{}): extracted fields drifted", golden.name, golden.schema_version);
+            // Ingest rejects envelopes whose timestamp is not RFC 3339
+            assert!(DateTime::parse_from_rfc3339(extracted["observed_at"].as_str().unwrap()).is_ok(), "{}", golden.name);
+        }
+    }
+
+    #[test]
+    fn test_golden_stored_envelope_is_the_received_bytes() {
+        for golden in envelope_fixtures::all() {
+            let body = golden.signed_event_body();
+            let payload = SignedEventRef::parse(&body).unwrap();
+            assert_eq!(payload.envelope.get(), golden.envelope, "{}", golden.name);
+            assert_eq!(hex::encode(Sha256::digest(payload.envelope.get().as_bytes())), golden.envelope_sha256(), "{}", golden.name);
+            // The producer canonical bytes arrive verbatim, so the payload_hash ingest recomputes matches
+            assert_eq!(golden.envelope_sha256(), golden.payload_hash(), "{}", golden.name);
+        }
+    }
+
+    #[test]
+    fn test_every_schema_version_stays_readable() {
+        for version in SCHEMA_VERSIONS {
+            let samples: Vec<&Golden> = envelope_fixtures::all().iter().filter(|g| g.schema_version == *version).collect();
+            assert!(!samples.is_empty(), "v{} has no golden samples", version);
+            for golden in samples {
+                let body = golden.signed_event_body();
+                let envelope = SignedEventRef::parse(&body).unwrap().envelope().unwrap();
+                assert_eq!(envelope.schema_version.unwrap_or(1), *version, "{}", golden.name);
+            }
+        }
+    }
+}
@@ -0,0 +1,172 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/envelope_parse_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for the single-parse signed event path - field extraction, raw byte reuse, and allocation reduction versus the previous serde_json::Value path
+
+/*
+ * Envelope Parse Tests
+ *
+ * The ingest hot path parses the request body once into SignedEventRef / EnvelopeRef and keeps
+ * envelope/data as raw slices. These tests check the extracted fields, that stored/hashed bytes
+ * are the received bytes, and measure heap allocations with a counting global allocator against
+ * the previous path (Value parse, to_value clone, to_vec for hashing, to_string of data).
+ */
+
+#[cfg(test)]
+mod tests {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    use serde_json::{json, Value};
+    use sha2::{Digest, Sha256};
+
+    use ingest::protocol::signed_event::{DpiEventData, LinuxEventData, SignedEventRef};
+
+    struct CountingAlloc;
+
+    // Per-thread so tests running in parallel do not skew each other's counts
+    thread_local! {
+        static ALLOCATIONS: Cell<(usize, usize)> = const { Cell::new((0, 0)) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAlloc {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|c| {
+                let (count, bytes) = c.get();
+                c.set((count + 1, bytes + layout.size()));
+            });
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static GLOBAL: CountingAlloc = CountingAlloc;
+
+    fn measure<F: FnOnce()>(f: F) -> (usize, usize) {
+        let (count, bytes) = ALLOCATIONS.with(|c| c.get());
+        f();
+        let (count_after, bytes_after) = ALLOCATIONS.with(|c| c.get());
+        (count_after - count, bytes_after - bytes)
+    }
+
+    fn linux_body() -> Vec<u8> {
+        let envelope = json!({
+            "event_id": "6f1c1f4e-8f7e-4b6f-9a55-0d2a3c1b9e01",
+            "timestamp": "2026-10-16T12:00:00+00:00",
+            "component": "linux_agent",
+            "component_id": "host-1",
+            "event_type": "process_telemetry",
+            "sequence": 42,
+            "signature": "c2ln".repeat(22),
+            "data": {
+                "event_category": "process",
+                "pid": 1234, "uid": 1000, "gid": 1000,
+                "process_data": {
+                    "event_type": "Exec", "ppid": 1000,
+                    "executable": "/usr/bin/test", "command_line": "test --arg",
+                    "mmap_address": null, "mmap_size": null
+                },
+                "filesystem_data": null,
+                "network_data": null,
+                "features": {
+                    "event_type": "process", "syscall_number": 59, "path_count": 1,
+                    "network_activity": false, "process_activity": true, "filesystem_activity": false
+                }
+            }
+        });
+        serde_json::to_vec(&json!({
+            "envelope": envelope,
+            "payload_hash": "ab".repeat(32),
+            "signature": "c2ln".repeat(22),
+            "signer_id": "host-1",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_linux_fields_extracted_from_single_parse() {
+        let body = linux_body();
+        let payload = SignedEventRef::parse(&body).unwrap();
+        let envelope = payload.envelope().unwrap();
+        let data: LinuxEventData = envelope.data().unwrap();
+
+        assert_eq!(payload.signer_id, "host-1");
+        assert_eq!(envelope.event_id, "6f1c1f4e-8f7e-4b6f-9a55-0d2a3c1b9e01");
+        assert_eq!(envelope.component_id, "host-1");
+        assert_eq!(data.event_category.as_deref(), Some("process"));
+        assert_eq!(data.pid, Some(1234));
+        let process = data.process_data.unwrap();
+        assert_eq!(process.executable.as_deref(), Some("/usr/bin/test"));
+        assert_eq!(process.command_line.as_deref(), Some("test --arg"));
+        assert!(data.filesystem_data.is_none());
+    }
+
+    #[test]
+    fn test_raw_envelope_bytes_match_value_serialization() {
+        // Stored bytes and hashes are unchanged for agents that send compact serde_json output
+        let body = linux_body();
+        let payload = SignedEventRef::parse(&body).unwrap();
+        let value: Value = serde_json::from_slice(&body).unwrap();
+
+        let legacy_hash = Sha256::digest(serde_json::to_vec(&value["envelope"]).unwrap());
+        assert_eq!(Sha256::digest(payload.envelope.get().as_bytes()), legacy_hash);
+        assert_eq!(payload.envelope().unwrap().data.get(), serde_json::to_string(&value["envelope"]["data"]).unwrap());
+    }
+
+    #[test]
+    fn test_malformed_bodies_rejected() {
+        assert!(SignedEventRef::parse(b"{}").is_err());
+        assert!(SignedEventRef::parse(b"not json").is_err());
+        let body = serde_json::to_vec(&json!({
+            "envelope": { "event_id": "x", "timestamp": "t" },
+            "payload_hash": "ab", "signature": "c2ln", "signer_id": "s"
+        }))
+        .unwrap();
+        let payload = SignedEventRef::parse(&body).unwrap();
+        assert!(payload.envelope().is_err());
+
+        let body = serde_json::to_vec(&json!({
+            "envelope": { "event_id": "x", "timestamp": "t", "component_id": "p", "data": { "src_port": "not-a-port" } },
+            "payload_hash": "ab", "signature": "c2ln", "signer_id": "s"
+        }))
+        .unwrap();
+        let payload = SignedEventRef::parse(&body).unwrap();
+        assert!(payload.envelope().unwrap().data::<DpiEventData>().is_err());
+    }
+
+    #[test]
+    fn test_single_parse_allocates_less_than_value_path() {
+        let body = linux_body();
+
+        let (legacy_allocs, legacy_bytes) = measure(|| {
+            let payload: Value = serde_json::from_slice(&body).unwrap();
+            let envelope = &payload["envelope"];
+            let full = serde_json::to_value(envelope).unwrap();
+            let hash = Sha256::digest(serde_json::to_vec(&full).unwrap());
+            let data_json = serde_json::to_string(&envelope["data"]).unwrap();
+            let data_hash = Sha256::digest(serde_json::to_vec(&envelope["data"]).unwrap());
+            std::hint::black_box((full, hash, data_json, data_hash));
+        });
+
+        let (typed_allocs, typed_bytes) = measure(|| {
+            let payload = SignedEventRef::parse(&body).unwrap();
+            let envelope = payload.envelope().unwrap();
+            let data: LinuxEventData = envelope.data().unwrap();
+            let stored = payload.envelope.to_owned();
+            let hash = Sha256::digest(payload.envelope.get().as_bytes());
+            let data_json = envelope.data.get().to_string();
+            let data_hash = Sha256::digest(envelope.data.get().as_bytes());
+            std::hint::black_box((data, stored, hash, data_json, data_hash));
+        });
+
+        println!(
+            "allocations: value path {} ({} bytes) -> single parse {} ({} bytes)",
+            legacy_allocs, legacy_bytes, typed_allocs, typed_bytes
+        );
+        assert!(typed_allocs * 3 < legacy_allocs, "{} vs {}", typed_allocs, legacy_allocs);
+        assert!(typed_bytes < legacy_bytes, "{} vs {}", typed_bytes, legacy_bytes);
+    }
+}
@@ -0,0 +1,93 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/envelope_validation_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for envelope validation errors - field paths, categories and expected types of refused signed events, and the 400 body returned to agents
+
+/*
+ * Envelope Validation Error Tests
+ *
+ * A signed event that is valid JSON but breaks the envelope contract names the failing field
+ * (as a dotted path from the request body), its category and the expected type, and comes back
+ * to the agent as a 400 with that body. Valid events parse exactly as before.
+ */
+
+#[cfg(test)]
+mod tests {
+    use axum::{http::StatusCode, response::IntoResponse};
+    use serde_json::json;
+
+    use ingest::envelope_validation::{self, IngestRejection, ValidationCategory, ValidationError};
+    use ingest::protocol::signed_event::{EnvelopeRef, LinuxEventData, SignedEventRef};
+
+    fn envelope(data: serde_json::Value) -> serde_json::Value {
+        json!({
+            "event_id": "3f1c2b9e-6a4d-4e8f-9b0a-1c2d3e4f5a6b",
+            "timestamp": "2026-10-16T12:00:00Z",
+            "component_id": "ws-0142",
+            "data": data,
+        })
+    }
+
+    #[test]
+    fn test_valid_event_parses_unchanged() {
+        let body = json!({ "envelope": envelope(json!({ "pid": 4242 })), "payload_hash": "ab", "signature": "c2ln", "signer_id": "k1" })
+            .to_string();
+        let signed: SignedEventRef = envelope_validation::from_slice("", body.as_bytes()).unwrap();
+        let env: EnvelopeRef = envelope_validation::from_str("envelope", signed.envelope.get()).unwrap();
+        let data: LinuxEventData = envelope_validation::from_str("envelope.data", env.data.get()).unwrap();
+        assert_eq!(data.pid, Some(4242));
+        assert_eq!(env.component_id, "ws-0142");
+    }
+
+    #[test]
+    fn test_errors_name_field_category_and_expected_type() {
+        // Missing header field of the signed event
+        let body = json!({ "envelope": envelope(json!({})), "payload_hash": "ab", "signer_id": "k1" }).to_string();
+        let err = envelope_validation::from_slice::<SignedEventRef>("", body.as_bytes()).unwrap_err();
+        assert_eq!((err.category, err.field.as_str()), (ValidationCategory::MissingField, "signature"));
+
+        // Missing envelope field
+        let mut env = envelope(json!({}));
+        env.as_object_mut().unwrap().remove("timestamp");
+        let env = env.to_string();
+        let err = envelope_validation::from_str::<EnvelopeRef>("envelope", &env).unwrap_err();
+        assert_eq!((err.category, err.field.as_str()), (ValidationCategory::MissingField, "envelope.timestamp"));
+        assert!(err.hint.contains("RFC 3339"));
+
+        // Wrong type, top level and nested in data
+        let data = json!({ "pid": "4242" }).to_string();
+        let err = envelope_validation::from_str::<LinuxEventData>("envelope.data", &data).unwrap_err();
+        assert_eq!((err.category, err.field.as_str()), (ValidationCategory::WrongType, "envelope.data.pid"));
+        assert_eq!(err.expected, "u64");
+        assert!(!err.message.contains("line"), "position leaked into {}", err.message);
+
+        let data = json!({ "process_data": { "ppid": -1 } }).to_string();
+        let err = envelope_validation::from_str::<LinuxEventData>("envelope.data", &data).unwrap_err();
+        assert_eq!(err.field, "envelope.data.process_data.ppid");
+        assert_eq!(err.category, ValidationCategory::InvalidValue);
+
+        // Not JSON at all
+        let err = envelope_validation::from_slice::<SignedEventRef>("", b"{\"envelope\": ").unwrap_err();
+        assert_eq!(err.category, ValidationCategory::MalformedJson);
+    }
+
+    #[tokio::test]
+    async fn test_rejection_body_is_returned_with_400() {
+        let err = ValidationError::new(ValidationCategory::InvalidValue, "envelope.timestamp", "RFC 3339 timestamp", "'yesterday' is not an RFC 3339 timestamp");
+        let response = IngestRejection::from(err).into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let bytes = axum::body::to_bytes(response.into_body(), 64 * 1024).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["error"], "envelope_validation_failed");
+        assert_eq!(body["category"], "invalid_value");
+        assert_eq!(body["field"], "envelope.timestamp");
+        assert_eq!(body["expected"], "RFC 3339 timestamp");
+        assert!(body["hint"].as_str().unwrap().starts_with("Send an RFC 3339 timestamp"));
+
+        // Other refusals keep a bare status
+        let response = IngestRejection::from(StatusCode::TOO_MANY_REQUESTS).into_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(axum::body::to_bytes(response.into_body(), 1024).await.unwrap().is_empty());
+
+        assert_eq!(ValidationError::empty("signer_id").category.as_str(), "missing_field");
+    }
+}
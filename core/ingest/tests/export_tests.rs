@@ -0,0 +1,156 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/export_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for data export - cursor tokens, export parameter validation, NDJSON line shape and export concurrency / rate limits
+
+/*
+ * Data Export Tests
+ *
+ * A cursor must resume the same session with the same filters, so tokens roundtrip exactly and
+ * anything else is refused. A new session needs an operator and a start time, and its window
+ * never reaches past the time it started.
+ */
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use chrono::{Duration as ChronoDuration, TimeZone, Utc};
+    use uuid::Uuid;
+
+    use ingest::export::{
+        ExportConfig, ExportCursor, ExportFilter, ExportLimiter, ExportLine, ExportParams, ExportRefusal, MAX_EXPORT_LIMIT,
+    };
+
+    fn filter() -> ExportFilter {
+        ExportFilter {
+            since: Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap(),
+            until: Utc.with_ymd_and_hms(2026, 1, 2, 0, 0, 0).unwrap(),
+            event_kinds: vec!["process".to_string()],
+            tenant: Some("acme".to_string()),
+        }
+    }
+
+    fn new_session() -> ExportParams {
+        ExportParams {
+            since: Some(Utc::now() - ChronoDuration::hours(1)),
+            requested_by: Some("analyst".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_cursor_roundtrip() {
+        let cursor = ExportCursor::new(filter());
+        assert_eq!(ExportCursor::decode(&cursor.encode()).unwrap(), cursor);
+
+        let moved = cursor.after(Utc.with_ymd_and_hms(2026, 1, 1, 12, 0, 0).unwrap(), Uuid::new_v4());
+        let decoded = ExportCursor::decode(&moved.encode()).unwrap();
+        assert_eq!(decoded, moved);
+        assert_eq!(decoded.session_id, cursor.session_id);
+        assert_eq!(decoded.filter, cursor.filter);
+    }
+
+    #[test]
+    fn test_cursor_rejects_garbage_and_other_versions() {
+        assert!(ExportCursor::decode("not-a-cursor").is_err());
+        let mut cursor = ExportCursor::new(filter());
+        cursor.version = 9;
+        let err = ExportCursor::decode(&cursor.encode()).unwrap_err();
+        assert!(err.contains("version"), "{}", err);
+    }
+
+    #[test]
+    fn test_new_session_requires_operator_and_since() {
+        let now = Utc::now();
+        let mut params = new_session();
+        params.requested_by = Some("  ".to_string());
+        assert!(params.start(now).is_err());
+
+        let mut params = new_session();
+        params.since = None;
+        assert!(params.start(now).is_err());
+
+        let start = new_session().start(now).unwrap();
+        assert!(start.new_session);
+        assert!(start.cursor.after.is_none());
+    }
+
+    #[test]
+    fn test_window_capped_at_now() {
+        let now = Utc::now();
+        let mut params = new_session();
+        params.until = Some(now + ChronoDuration::days(1));
+        assert_eq!(params.start(now).unwrap().cursor.filter.until, now);
+
+        params.since = Some(now + ChronoDuration::minutes(5));
+        assert!(params.start(now).is_err());
+    }
+
+    #[test]
+    fn test_filters_parsed() {
+        let mut params = new_session();
+        params.event_kind = Some("process, file,,network".to_string());
+        params.tenant = Some(" acme ".to_string());
+        let f = params.start(Utc::now()).unwrap().cursor.filter;
+        assert_eq!(f.event_kinds, vec!["process", "file", "network"]);
+        assert_eq!(f.tenant.as_deref(), Some("acme"));
+
+        params.event_kind = Some((0..65).map(|i| format!("k{}", i)).collect::<Vec<_>>().join(","));
+        assert!(params.start(Utc::now()).is_err());
+    }
+
+    #[test]
+    fn test_cursor_excludes_filters_and_bounds_limit() {
+        let token = ExportCursor::new(filter()).encode();
+        let resumed = ExportParams { cursor: Some(token.clone()), ..Default::default() };
+        let start = resumed.start(Utc::now()).unwrap();
+        assert!(!start.new_session);
+        assert_eq!(start.cursor.filter, filter());
+
+        let mixed = ExportParams { cursor: Some(token.clone()), tenant: Some("other".to_string()), ..Default::default() };
+        assert!(mixed.start(Utc::now()).is_err());
+
+        for limit in [0, MAX_EXPORT_LIMIT + 1] {
+            let params = ExportParams { cursor: Some(token.clone()), limit: Some(limit), ..Default::default() };
+            assert!(params.start(Utc::now()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_end_line_shape() {
+        let line = ExportLine::End { next_cursor: None, exported: 3 }.to_ndjson();
+        assert!(line.ends_with('\n'));
+        let v: serde_json::Value = serde_json::from_str(line.trim_end()).unwrap();
+        assert_eq!(v["type"], "end");
+        assert!(v["next_cursor"].is_null());
+        assert_eq!(v["exported"], 3);
+    }
+
+    #[test]
+    fn test_limiter_concurrency() {
+        let limiter = ExportLimiter::new(ExportConfig { max_concurrent: 1, requests_per_minute: 100 });
+        let permit = limiter.acquire().unwrap();
+        assert!(matches!(limiter.acquire(), Err(ExportRefusal::TooManyConcurrent)));
+        permit.count_rows(5);
+        drop(permit);
+        assert!(limiter.acquire().is_ok());
+
+        let stats = limiter.stats();
+        assert_eq!(stats.active_streams, 0);
+        assert_eq!(stats.rows_exported, 5);
+        assert_eq!(stats.refused, 1);
+    }
+
+    #[test]
+    fn test_limiter_rate_window() {
+        let limiter = ExportLimiter::new(ExportConfig { max_concurrent: 10, requests_per_minute: 2 });
+        let t0 = Instant::now();
+        assert!(limiter.acquire_at(t0).is_ok());
+        assert!(limiter.acquire_at(t0).is_ok());
+        match limiter.acquire_at(t0 + Duration::from_secs(20)) {
+            Err(ExportRefusal::RateLimited { retry_after_secs }) => assert!((1..=40).contains(&retry_after_secs)),
+            other => panic!("expected rate limit, got {:?}", other.map(|_| ())),
+        }
+        assert!(limiter.acquire_at(t0 + Duration::from_secs(61)).is_ok());
+    }
+}
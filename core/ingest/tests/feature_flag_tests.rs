@@ -0,0 +1,80 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/feature_flag_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for runtime feature flags - tenant override over global value over default, snapshot replacement and flag/tenant validation
+
+/*
+ * Feature Flag Tests
+ *
+ * A tenant override wins over the global value, which wins over the flag default; a reload
+ * replaces the whole snapshot, and rows naming flags this build does not know are ignored.
+ */
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use ingest::feature_flags::{self, FeatureFlags, Flag, FlagSetting, FlagSource};
+
+    fn setting(flag: &str, tenant_id: Option<&str>, enabled: bool) -> FlagSetting {
+        FlagSetting {
+            flag: flag.to_string(),
+            tenant_id: tenant_id.map(str::to_string),
+            enabled,
+            reason: "CHG-1042".to_string(),
+            updated_by: "soc-lead".to_string(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_defaults_apply_without_rows() {
+        let flags = FeatureFlags::new();
+        for flag in Flag::ALL {
+            assert_eq!(flags.resolve(flag, None), (true, FlagSource::Default));
+            assert_eq!(flags.resolve(flag, Some("acme")), (true, FlagSource::Default));
+        }
+    }
+
+    #[test]
+    fn test_tenant_override_wins_over_global_value() {
+        let flags = FeatureFlags::new();
+        flags.replace(&[
+            setting("memory_acquisition", None, false),
+            setting("memory_acquisition", Some("acme"), true),
+            setting("pcap_capture", Some("globex"), false),
+        ]);
+        assert_eq!(flags.resolve(Flag::MemoryAcquisition, None), (false, FlagSource::Global));
+        assert_eq!(flags.resolve(Flag::MemoryAcquisition, Some("globex")), (false, FlagSource::Global));
+        assert_eq!(flags.resolve(Flag::MemoryAcquisition, Some("acme")), (true, FlagSource::Tenant));
+        assert!(!flags.is_enabled(Flag::PcapCapture, Some("globex")));
+        assert!(flags.is_enabled(Flag::PcapCapture, Some("acme")));
+        assert!(flags.is_enabled(Flag::PcapCapture, None));
+    }
+
+    #[test]
+    fn test_reload_replaces_snapshot_and_ignores_unknown_flags() {
+        let flags = FeatureFlags::new();
+        flags.replace(&[setting("yara_scanning", None, false), setting("sandbox_detonation", Some("acme"), false)]);
+        assert!(!flags.is_enabled(Flag::YaraScanning, None));
+
+        // Cleared rows fall back to the default; a flag from a newer build changes nothing
+        flags.replace(&[setting("remote_shell", None, true)]);
+        assert_eq!(flags.resolve(Flag::YaraScanning, None), (true, FlagSource::Default));
+        assert_eq!(flags.resolve(Flag::SandboxDetonation, Some("acme")), (true, FlagSource::Default));
+    }
+
+    #[test]
+    fn test_flag_keys_and_tenants_are_validated() {
+        for flag in Flag::ALL {
+            assert_eq!(Flag::parse(flag.as_str()), Some(flag));
+        }
+        assert_eq!(Flag::parse("Memory_Acquisition"), None);
+        assert_eq!(Flag::parse(""), None);
+
+        assert!(feature_flags::check_tenant("acme-eu").is_ok());
+        assert!(feature_flags::check_tenant("").is_err());
+        assert!(feature_flags::check_tenant(" acme").is_err());
+        assert!(feature_flags::check_tenant("acme\n").is_err());
+        assert!(feature_flags::check_tenant(&"a".repeat(feature_flags::MAX_TENANT_CHARS + 1)).is_err());
+    }
+}
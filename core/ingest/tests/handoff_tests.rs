@@ -0,0 +1,91 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/handoff_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for zero-downtime ingest restarts - SO_REUSEPORT listeners, handoff file announcement and successor detection, drain signalling and in-flight tracking
+
+/*
+ * Handoff Tests
+ *
+ * Tests that two instances can hold the same address with SO_REUSEPORT, that a handoff file on a
+ * plain bind is refused, that only a different live process counts as a successor, and that
+ * draining wakes waiters once while counting in-flight requests.
+ */
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use uuid::Uuid;
+
+    use ingest::handoff::{self, DrainController, HandoffRecord, ListenerConfig, ListenerSource};
+
+    fn cfg(reuse_port: bool, handoff_file: Option<PathBuf>) -> ListenerConfig {
+        ListenerConfig { reuse_port, handoff_file, drain_timeout: Duration::from_secs(5) }
+    }
+
+    fn temp_file(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("ransomeye-{}-{}", name, Uuid::new_v4()))
+    }
+
+    #[tokio::test]
+    async fn test_reuseport_allows_second_listener() {
+        let (first, source) = handoff::acquire_listener("127.0.0.1:0", &cfg(true, None)).unwrap();
+        assert_eq!(source, ListenerSource::ReusePort);
+        assert!(source.supports_handoff());
+        let addr = first.local_addr().unwrap().to_string();
+
+        let (_second, _) = handoff::acquire_listener(&addr, &cfg(true, None)).unwrap();
+        assert!(handoff::acquire_listener(&addr, &cfg(false, None)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_handoff_requires_shared_socket() {
+        let err = handoff::acquire_listener("127.0.0.1:0", &cfg(false, Some(temp_file("handoff")))).unwrap_err();
+        assert!(err.contains("FAIL-CLOSED"));
+    }
+
+    #[test]
+    fn test_announce_successor_and_release() {
+        let path = temp_file("handoff");
+        let own = Uuid::new_v4();
+        assert_eq!(handoff::read_record(&path).unwrap(), None);
+
+        let record = HandoffRecord::new(own, "127.0.0.1:8080");
+        handoff::announce(&path, &record).unwrap();
+        assert_eq!(handoff::read_record(&path).unwrap(), Some(record.clone()));
+        // Our own announcement never triggers a drain
+        assert!(!handoff::successor(&record, own));
+        // A different boot in a dead process is not a successor either
+        let dead = HandoffRecord { pid: u32::MAX, boot_id: Uuid::new_v4(), ..record.clone() };
+        assert!(!handoff::successor(&dead, own));
+
+        // Release only removes the file while it still names this instance
+        handoff::release(&path, Uuid::new_v4());
+        assert!(path.exists());
+        handoff::release(&path, own);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_drain_wakes_waiters_once_and_tracks_in_flight() {
+        let drain = Arc::new(DrainController::new());
+        let guard = drain.track();
+        assert_eq!(drain.in_flight(), 1);
+
+        let waiter = {
+            let drain = drain.clone();
+            tokio::spawn(async move { drain.wait().await })
+        };
+        tokio::task::yield_now().await;
+        assert!(drain.begin("sigterm"));
+        assert!(!drain.begin("handoff"));
+        tokio::time::timeout(Duration::from_secs(1), waiter).await.unwrap().unwrap();
+        assert_eq!(drain.reason().as_deref(), Some("sigterm"));
+
+        drop(guard);
+        assert_eq!(drain.in_flight(), 0);
+        // Waiting after the drain started returns immediately
+        tokio::time::timeout(Duration::from_millis(100), drain.wait()).await.unwrap();
+    }
+}
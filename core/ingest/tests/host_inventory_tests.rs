@@ -0,0 +1,97 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/host_inventory_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for host inventory reports - validation into a host_inventory row, replacement by newer reports and no regression to older ones
+
+/*
+ * Host Inventory Tests
+ *
+ * Run against the SQLite lab backend: a valid report becomes the agent's host_inventory row, a
+ * newer report replaces it, a replayed older report leaves it untouched, and malformed reports
+ * are refused before anything is written.
+ */
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, TimeZone, Utc};
+    use uuid::Uuid;
+
+    use ingest::host_inventory;
+    use ingest::protocol::signed_event::{LinuxHostInventory, LinuxSecurityTool};
+    use ingest::storage::{DiskEncryption, SqliteStore, TelemetrySource, TelemetryStore};
+
+    fn report(kernel_release: &str) -> LinuxHostInventory {
+        LinuxHostInventory {
+            hostname: Some("lab-host-1".to_string()),
+            os_name: Some("Ubuntu".to_string()),
+            os_version: Some("22.04".to_string()),
+            kernel_release: Some(kernel_release.to_string()),
+            arch: Some("x86_64".to_string()),
+            cpu_cores: Some(8),
+            memory_total_bytes: Some(16 << 30),
+            security_tools: vec![LinuxSecurityTool { name: "auditd".to_string(), kind: "audit".to_string() }],
+            disk_encryption: Some("encrypted".to_string()),
+            encrypted_volumes: vec!["luks-root".to_string()],
+            ..Default::default()
+        }
+    }
+
+    fn at(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 14, hour, 0, 0).unwrap()
+    }
+
+    async fn apply(store: &SqliteStore, agent_id: Uuid, collected_at: DateTime<Utc>, kernel_release: &str) -> bool {
+        let record = host_inventory::to_record(agent_id, &Uuid::new_v4().to_string(), collected_at, &report(kernel_release)).unwrap();
+        let mut tx = store.begin().await.unwrap();
+        let written = tx.upsert_host_inventory(&record).await.unwrap();
+        tx.commit().await.unwrap();
+        written
+    }
+
+    #[tokio::test]
+    async fn test_report_is_stored_per_agent() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let agent_id = store.resolve_agent("lab-host-1", TelemetrySource::LinuxAgent).await.unwrap();
+
+        assert!(apply(&store, agent_id, at(8), "5.15.0-91-generic").await);
+
+        let stored = store.host_inventory(agent_id).await.unwrap().unwrap();
+        assert_eq!(stored.kernel_release, "5.15.0-91-generic");
+        assert_eq!(stored.disk_encryption, DiskEncryption::Encrypted);
+        assert_eq!(stored.security_tools[0].name, "auditd");
+        assert_eq!(stored.encrypted_volumes, vec!["luks-root".to_string()]);
+        assert_eq!(stored.memory_total_bytes, 16 << 30);
+        assert_eq!(stored.collected_at, at(8));
+        assert_eq!(store.list_host_inventory(100).await.unwrap().len(), 1);
+        assert!(store.host_inventory(Uuid::new_v4()).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_older_report_never_replaces_newer() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let agent_id = store.resolve_agent("lab-host-1", TelemetrySource::LinuxAgent).await.unwrap();
+
+        assert!(apply(&store, agent_id, at(8), "5.15.0-91-generic").await);
+        assert!(apply(&store, agent_id, at(9), "5.15.0-94-generic").await);
+        // Spooled report from before the kernel upgrade arrives late
+        assert!(!apply(&store, agent_id, at(7), "5.15.0-88-generic").await);
+
+        let stored = store.host_inventory(agent_id).await.unwrap().unwrap();
+        assert_eq!(stored.kernel_release, "5.15.0-94-generic");
+        assert_eq!(stored.collected_at, at(9));
+    }
+
+    #[test]
+    fn test_malformed_reports_are_refused() {
+        let agent_id = Uuid::new_v4();
+        let refused = |r: LinuxHostInventory| host_inventory::to_record(agent_id, "event-1", at(8), &r).is_err();
+
+        assert!(refused(LinuxHostInventory { kernel_release: None, ..report("") }));
+        assert!(refused(LinuxHostInventory { arch: Some(" ".to_string()), ..report("6.1.0") }));
+        assert!(refused(LinuxHostInventory { hostname: Some("h".repeat(300)), ..report("6.1.0") }));
+        assert!(refused(LinuxHostInventory { disk_encryption: Some("bitlocker".to_string()), ..report("6.1.0") }));
+        assert!(refused(LinuxHostInventory { encrypted_volumes: vec!["v".to_string(); 65], ..report("6.1.0") }));
+
+        let unreported = host_inventory::to_record(agent_id, "event-1", at(8), &LinuxHostInventory { disk_encryption: None, ..report("6.1.0") });
+        assert_eq!(unreported.unwrap().disk_encryption, DiskEncryption::Unknown);
+    }
+}
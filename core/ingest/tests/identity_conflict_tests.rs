@@ -0,0 +1,156 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/identity_conflict_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for duplicate agent-id detection - concurrent origins open one conflict, moves after a quiet window do not, resolution lifts quarantine
+
+/*
+ * Identity Conflict Tests
+ *
+ * One identity from two origins inside the window opens exactly one conflict and quarantines
+ * every later event of that identity. An origin change after a quiet window is a move. Resolving
+ * the conflict forgets the old origin; detect mode reports without quarantining.
+ */
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    use ingest::identity_conflict::{ConflictMode, IdentityConflicts, IdentityDecision};
+    use ingest::storage::IdentityOrigin;
+
+    const WINDOW: Duration = Duration::from_secs(300);
+
+    fn origin(ip: &str, host_id: Option<&str>) -> IdentityOrigin {
+        IdentityOrigin {
+            source_ip: Some(ip.parse().unwrap()),
+            host_id: host_id.map(str::to_string),
+        }
+    }
+
+    fn observe(conflicts: &IdentityConflicts, origin: &IdentityOrigin, at: Instant) -> IdentityDecision {
+        conflicts.observe_at("linux_agent", "host-a", Uuid::nil(), origin, at, Utc::now())
+    }
+
+    #[test]
+    fn test_concurrent_origins_open_one_conflict_and_quarantine() {
+        let conflicts = IdentityConflicts::new(ConflictMode::Quarantine, WINDOW);
+        let t0 = Instant::now();
+        let a = origin("10.0.0.1", None);
+        let b = origin("10.0.0.2", None);
+
+        assert!(matches!(observe(&conflicts, &a, t0), IdentityDecision::Accepted));
+        assert!(matches!(observe(&conflicts, &a, t0 + Duration::from_secs(10)), IdentityDecision::Accepted));
+
+        let conflict = match observe(&conflicts, &b, t0 + Duration::from_secs(20)) {
+            IdentityDecision::Opened(c) => c,
+            other => panic!("expected a new conflict, got {:?}", other),
+        };
+        assert_eq!(conflict.first_origin, a);
+        assert_eq!(conflict.conflicting_origin, b);
+
+        // Both origins are held from now on, under the same conflict
+        for o in [&a, &b] {
+            match observe(&conflicts, o, t0 + Duration::from_secs(30)) {
+                IdentityDecision::Quarantined(id) => assert_eq!(id, conflict.conflict_id),
+                other => panic!("expected quarantine, got {:?}", other),
+            }
+        }
+        assert_eq!(conflicts.open_conflicts().len(), 1);
+
+        // Other identities are unaffected
+        let other = conflicts.observe_at("linux_agent", "host-b", Uuid::nil(), &b, t0, Utc::now());
+        assert!(matches!(other, IdentityDecision::Accepted));
+    }
+
+    #[test]
+    fn test_host_id_distinguishes_hosts_behind_one_address() {
+        let conflicts = IdentityConflicts::new(ConflictMode::Quarantine, WINDOW);
+        let t0 = Instant::now();
+
+        // host_id learned from a later event of the same origin
+        assert!(matches!(observe(&conflicts, &origin("192.0.2.10", None), t0), IdentityDecision::Accepted));
+        assert!(matches!(observe(&conflicts, &origin("192.0.2.10", Some("m-1")), t0), IdentityDecision::Accepted));
+        assert!(matches!(
+            observe(&conflicts, &origin("192.0.2.10", Some("m-2")), t0),
+            IdentityDecision::Opened(_)
+        ));
+    }
+
+    #[test]
+    fn test_origin_change_after_quiet_window_is_a_move() {
+        let conflicts = IdentityConflicts::new(ConflictMode::Quarantine, WINDOW);
+        let t0 = Instant::now();
+        let b = origin("10.0.0.2", None);
+
+        assert!(matches!(observe(&conflicts, &origin("10.0.0.1", None), t0), IdentityDecision::Accepted));
+        assert!(matches!(observe(&conflicts, &b, t0 + WINDOW), IdentityDecision::Accepted));
+        assert!(matches!(observe(&conflicts, &b, t0 + WINDOW + Duration::from_secs(1)), IdentityDecision::Accepted));
+        assert!(conflicts.open_conflicts().is_empty());
+    }
+
+    #[test]
+    fn test_resolve_lifts_quarantine_and_forgets_origin() {
+        let conflicts = IdentityConflicts::new(ConflictMode::Quarantine, WINDOW);
+        let t0 = Instant::now();
+        let a = origin("10.0.0.1", None);
+        let b = origin("10.0.0.2", None);
+        observe(&conflicts, &a, t0);
+        let conflict_id = match observe(&conflicts, &b, t0) {
+            IdentityDecision::Opened(c) => c.conflict_id,
+            other => panic!("expected a new conflict, got {:?}", other),
+        };
+
+        assert!(conflicts.resolve(conflict_id).is_some());
+        assert!(conflicts.resolve(conflict_id).is_none());
+        assert!(conflicts.get(conflict_id).is_none());
+
+        // Next event re-establishes the origin; the other host opens a fresh conflict
+        assert!(matches!(observe(&conflicts, &b, t0), IdentityDecision::Accepted));
+        match observe(&conflicts, &a, t0) {
+            IdentityDecision::Opened(c) => assert_ne!(c.conflict_id, conflict_id),
+            other => panic!("expected a new conflict, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_detect_mode_reports_once_without_quarantine() {
+        let conflicts = IdentityConflicts::new(ConflictMode::Detect, WINDOW);
+        let t0 = Instant::now();
+        let a = origin("10.0.0.1", None);
+        let b = origin("10.0.0.2", None);
+
+        observe(&conflicts, &a, t0);
+        assert!(matches!(observe(&conflicts, &b, t0), IdentityDecision::Opened(_)));
+        assert!(matches!(observe(&conflicts, &b, t0), IdentityDecision::Accepted));
+        assert_eq!(conflicts.open_conflicts().len(), 1);
+    }
+
+    #[test]
+    fn test_restored_conflict_quarantines_after_restart() {
+        let first_run = IdentityConflicts::new(ConflictMode::Quarantine, WINDOW);
+        let t0 = Instant::now();
+        observe(&first_run, &origin("10.0.0.1", None), t0);
+        observe(&first_run, &origin("10.0.0.2", None), t0);
+
+        let restarted = IdentityConflicts::new(ConflictMode::Quarantine, WINDOW);
+        restarted.restore(first_run.open_conflicts());
+        assert!(matches!(
+            observe(&restarted, &origin("10.0.0.1", None), t0),
+            IdentityDecision::Quarantined(_)
+        ));
+    }
+
+    #[test]
+    fn test_disabled_mode_and_parsing() {
+        let conflicts = IdentityConflicts::new(ConflictMode::Disabled, WINDOW);
+        let t0 = Instant::now();
+        observe(&conflicts, &origin("10.0.0.1", None), t0);
+        assert!(matches!(observe(&conflicts, &origin("10.0.0.2", None), t0), IdentityDecision::Accepted));
+
+        assert_eq!(ConflictMode::parse("quarantine").unwrap(), ConflictMode::Quarantine);
+        assert_eq!(ConflictMode::parse("detect").unwrap().as_str(), "detect");
+        assert!(ConflictMode::parse("block").is_err());
+    }
+}
@@ -0,0 +1,65 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/key_pinning_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for agent signing key pinning - pin matching, rotation grace window, and mode parsing
+
+/*
+ * Key Pinning Tests
+ *
+ * A pin accepts its own key, accepts the replaced key only until the rotation grace window
+ * ends, and rejects everything else. Disabled pinning accepts without touching the database.
+ */
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use uuid::Uuid;
+
+    use ingest::key_pinning::{AgentKeyPin, KeyPinMode, KeyPinning, PinDecision};
+
+    fn pin(previous_valid_for: Option<Duration>) -> AgentKeyPin {
+        AgentKeyPin {
+            signer_id: "agent-key-2".to_string(),
+            previous_signer_id: previous_valid_for.map(|_| "agent-key-1".to_string()),
+            previous_valid_until: previous_valid_for.map(|d| Utc::now() + d),
+        }
+    }
+
+    #[test]
+    fn test_pin_accepts_only_pinned_key() {
+        let p = pin(None);
+        assert!(p.accepts("agent-key-2", Utc::now()));
+        assert!(!p.accepts("agent-key-1", Utc::now()));
+        assert!(!p.accepts("attacker-key", Utc::now()));
+    }
+
+    #[test]
+    fn test_replaced_key_accepted_only_within_grace() {
+        let p = pin(Some(Duration::minutes(10)));
+        assert!(p.accepts("agent-key-1", Utc::now()));
+        assert!(!p.accepts("agent-key-1", Utc::now() + Duration::minutes(11)));
+        assert!(!p.accepts("attacker-key", Utc::now()));
+    }
+
+    #[test]
+    fn test_mode_parse() {
+        assert_eq!(KeyPinMode::parse("enforce").unwrap(), KeyPinMode::Enforce);
+        assert_eq!(KeyPinMode::parse("detect").unwrap(), KeyPinMode::Detect);
+        assert_eq!(KeyPinMode::parse("disabled").unwrap(), KeyPinMode::Disabled);
+        assert!(KeyPinMode::parse("ENFORCE").is_err());
+        assert!(KeyPinMode::parse("").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_disabled_pinning_accepts_any_key() {
+        let pins = KeyPinning::new(KeyPinMode::Disabled, None, Duration::hours(1));
+        let agent_id = Uuid::new_v4();
+        assert!(matches!(pins.check(agent_id, "key-a").await.unwrap(), PinDecision::Accepted));
+        assert!(matches!(pins.check(agent_id, "key-b").await.unwrap(), PinDecision::Accepted));
+    }
+
+    #[tokio::test]
+    async fn test_enforcing_without_control_plane_fails_closed() {
+        let pins = KeyPinning::new(KeyPinMode::Enforce, None, Duration::hours(1));
+        assert!(pins.check(Uuid::new_v4(), "key-a").await.is_err());
+    }
+}
@@ -0,0 +1,140 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/lineage_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for process lineage verification - agent-compatible hashing, tampered events, missing parents after the grace window, out-of-order delivery and streams picked up mid-chain
+
+/*
+ * Lineage Tests
+ *
+ * Hashes match the agent's scheme (shared test vector). A chain delivered from its genesis
+ * verifies clean even out of order; an altered event is tampered; a child whose parent never
+ * arrives within the grace window is a broken link, reported once. A stream first seen mid-chain
+ * adopts its unknown parents.
+ */
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use uuid::Uuid;
+
+    use ingest::lineage::{genesis_hash, lineage_hash, LineageEvent, LineageFinding, LineageMode, LineageVerifier};
+    use ingest::protocol::signed_event::LinuxLineage;
+
+    const GRACE: Duration = Duration::from_secs(300);
+    const BOOT: &str = "boot-a";
+    const CHAIN: &str = "chain-1";
+
+    fn exec(pid: u64, ppid: u64) -> LineageEvent<'static> {
+        LineageEvent {
+            event_type: "Exec",
+            pid,
+            ppid: Some(ppid),
+            uid: 1000,
+            gid: 1000,
+            executable: Some("/usr/bin/test"),
+            command_line: Some("test --arg"),
+            mmap_address: None,
+            mmap_size: None,
+        }
+    }
+
+    fn link(event: &LineageEvent<'_>, parent_hash: &str) -> LinuxLineage {
+        LinuxLineage {
+            boot_id: BOOT.to_string(),
+            chain_id: CHAIN.to_string(),
+            hash: lineage_hash(BOOT, event, parent_hash),
+            parent_hash: parent_hash.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_hash_matches_agent_scheme() {
+        let genesis = genesis_hash(BOOT, CHAIN);
+        assert_eq!(genesis, "182b4c88692b8e3788d37aa54ccdebc02accc8d262850e97a7f87b885e3f25df");
+        assert_eq!(
+            lineage_hash(BOOT, &exec(100, 1), &genesis),
+            "68813016eb2524c156722043ceaa4822e42c6cbcb794470ee36739e5bb1041d1"
+        );
+    }
+
+    #[test]
+    fn test_chain_from_genesis_verifies_even_out_of_order() {
+        let verifier = LineageVerifier::new(LineageMode::Detect, GRACE);
+        let agent = Uuid::new_v4();
+        let t0 = Instant::now();
+        let root = link(&exec(100, 1), &genesis_hash(BOOT, CHAIN));
+        let child = link(&exec(200, 100), &root.hash);
+        let grandchild = link(&exec(300, 200), &child.hash);
+
+        assert!(verifier.observe_at(agent, &exec(100, 1), &root, t0).is_empty());
+        // Spooled child arrives after its own child
+        assert!(verifier.observe_at(agent, &exec(300, 200), &grandchild, t0).is_empty());
+        assert!(verifier.observe_at(agent, &exec(200, 100), &child, t0 + Duration::from_secs(10)).is_empty());
+        // Nothing left pending once the grace window passes
+        let later = link(&exec(400, 300), &grandchild.hash);
+        assert!(verifier.observe_at(agent, &exec(400, 300), &later, t0 + GRACE * 2).is_empty());
+    }
+
+    #[test]
+    fn test_altered_event_is_tampered() {
+        let verifier = LineageVerifier::new(LineageMode::Detect, GRACE);
+        let root = link(&exec(100, 1), &genesis_hash(BOOT, CHAIN));
+        let mut altered = exec(100, 1);
+        altered.command_line = Some("test --other");
+
+        let findings = verifier.observe_at(Uuid::new_v4(), &altered, &root, Instant::now());
+        assert_eq!(findings.len(), 1);
+        assert!(matches!(&findings[0], LineageFinding::TamperedEvent { hash, .. } if *hash == root.hash));
+    }
+
+    #[test]
+    fn test_missing_parent_is_broken_link_after_grace() {
+        let verifier = LineageVerifier::new(LineageMode::Detect, GRACE);
+        let agent = Uuid::new_v4();
+        let t0 = Instant::now();
+        let root = link(&exec(100, 1), &genesis_hash(BOOT, CHAIN));
+        let missing = link(&exec(200, 100), &root.hash);
+        let orphan = link(&exec(300, 200), &missing.hash);
+
+        assert!(verifier.observe_at(agent, &exec(100, 1), &root, t0).is_empty());
+        assert!(verifier.observe_at(agent, &exec(300, 200), &orphan, t0).is_empty());
+
+        let next = link(&exec(101, 100), &root.hash);
+        let findings = verifier.observe_at(agent, &exec(101, 100), &next, t0 + GRACE);
+        assert_eq!(
+            findings,
+            vec![LineageFinding::BrokenLink {
+                boot_id: BOOT.to_string(),
+                chain_id: CHAIN.to_string(),
+                parent_hash: missing.hash.clone(),
+                child_hash: orphan.hash.clone(),
+            }]
+        );
+        // Reported once
+        let again = link(&exec(102, 100), &root.hash);
+        assert!(verifier.observe_at(agent, &exec(102, 100), &again, t0 + GRACE * 2).is_empty());
+    }
+
+    #[test]
+    fn test_stream_seen_mid_chain_adopts_unknown_parents() {
+        let verifier = LineageVerifier::new(LineageMode::Detect, GRACE);
+        let agent = Uuid::new_v4();
+        let t0 = Instant::now();
+        let unseen = link(&exec(100, 1), &genesis_hash(BOOT, CHAIN));
+        let first = link(&exec(200, 100), &unseen.hash);
+        let other = link(&exec(300, 100), &"ab".repeat(32));
+
+        assert!(verifier.observe_at(agent, &exec(200, 100), &first, t0).is_empty());
+        assert!(verifier.observe_at(agent, &exec(300, 100), &other, t0).is_empty());
+        let next = link(&exec(201, 200), &first.hash);
+        assert!(verifier.observe_at(agent, &exec(201, 200), &next, t0 + GRACE * 2).is_empty());
+    }
+
+    #[test]
+    fn test_disabled_mode_reports_nothing() {
+        let verifier = LineageVerifier::new(LineageMode::Disabled, GRACE);
+        let mut forged = link(&exec(100, 1), &genesis_hash(BOOT, CHAIN));
+        forged.hash = "00".repeat(32);
+        assert!(verifier.observe_at(Uuid::new_v4(), &exec(100, 1), &forged, Instant::now()).is_empty());
+    }
+}
@@ -0,0 +1,119 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/list_query_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for the shared list API conventions - query parsing, filter operators, sparse fieldsets and stable cursor pagination
+
+/*
+ * List Query Tests
+ *
+ * Walking a list with next_cursor visits every matching item exactly once, in sort-key order,
+ * even when items are added between pages. Unknown parameters, fields and operators are
+ * rejected with a reason instead of being ignored.
+ */
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+
+    use ingest::http_list::{FilterOp, ListQuery, ListSpec};
+
+    #[derive(Serialize)]
+    struct Host {
+        id: u32,
+        name: String,
+        score: f64,
+        site: Site,
+    }
+
+    #[derive(Serialize)]
+    struct Site {
+        zone: String,
+    }
+
+    const SPEC: ListSpec = ListSpec {
+        filterable: &["name", "score", "site.zone"],
+        selectable: &["id", "name", "site"],
+    };
+
+    fn hosts(n: u32) -> Vec<Host> {
+        (1..=n)
+            .map(|id| Host {
+                id,
+                name: format!("host-{:02}", id),
+                score: id as f64 / 10.0,
+                site: Site { zone: if id % 2 == 0 { "dmz" } else { "core" }.to_string() },
+            })
+            .collect()
+    }
+
+    fn key(h: &Host) -> String {
+        format!("{:08}", h.id)
+    }
+
+    fn ids(page: &ingest::http_list::Page) -> Vec<u64> {
+        page.data.iter().map(|v| v["id"].as_u64().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_parse_percent_encoded_filters_and_fields() {
+        let q = ListQuery::parse("limit=2&filter%5Bsite.zone%5D=dmz&filter[score][gte]=0.5&fields=id,name").unwrap();
+        assert_eq!(q.limit, 2);
+        assert_eq!(q.filters.len(), 2);
+        assert_eq!(q.filters[0].field, "site.zone");
+        assert_eq!(q.filters[0].op, FilterOp::Eq);
+        assert_eq!(q.filters[1].op, FilterOp::Gte);
+        assert_eq!(q.fields.as_deref(), Some(&["id".to_string(), "name".to_string()][..]));
+
+        assert_eq!(ListQuery::parse("").unwrap(), ListQuery::default());
+        assert!(ListQuery::parse("limit=0").is_err());
+        assert!(ListQuery::parse("limit=100000").is_err());
+        assert!(ListQuery::parse("page=2").is_err());
+        assert!(ListQuery::parse("filter[name][like]=x").is_err());
+        assert!(ListQuery::parse("filter[]=x").is_err());
+        assert!(ListQuery::parse("fields=,").is_err());
+    }
+
+    #[test]
+    fn test_cursor_walk_is_complete_and_stable_under_inserts() {
+        let q = ListQuery { limit: 3, ..ListQuery::default() };
+        let first = q.paginate(&SPEC, hosts(7), key).unwrap();
+        assert_eq!(ids(&first), vec![1, 2, 3]);
+        assert_eq!(first.total_estimate, 7);
+
+        // A row sorting before the cursor appears between pages; it is not served twice or shifted in
+        let mut grown = hosts(7);
+        grown.push(Host { id: 0, name: "host-00".into(), score: 0.0, site: Site { zone: "core".into() } });
+        let q2 = ListQuery { cursor: first.next_cursor.clone(), ..q.clone() };
+        let second = q2.paginate(&SPEC, grown, key).unwrap();
+        assert_eq!(ids(&second), vec![4, 5, 6]);
+
+        let q3 = ListQuery { cursor: second.next_cursor.clone(), ..q.clone() };
+        let last = q3.paginate(&SPEC, hosts(7), key).unwrap();
+        assert_eq!(ids(&last), vec![7]);
+        assert!(last.next_cursor.is_none());
+
+        let bad = ListQuery { cursor: Some("not base64!".into()), ..q };
+        assert!(bad.paginate(&SPEC, hosts(7), key).is_err());
+    }
+
+    #[test]
+    fn test_filters_and_sparse_fieldsets() {
+        let q = ListQuery::parse("filter[site.zone]=dmz&filter[score][gt]=0.3&fields=id,site").unwrap();
+        let page = q.paginate(&SPEC, hosts(8), key).unwrap();
+        assert_eq!(ids(&page), vec![4, 6, 8]);
+        assert_eq!(page.total_estimate, 3);
+        let obj = page.data[0].as_object().unwrap();
+        assert_eq!(obj.len(), 2);
+        assert_eq!(obj["site"]["zone"], "dmz");
+
+        let q = ListQuery::parse("filter[name][in]=host-02,host-05&filter[score][ne]=0.2").unwrap();
+        assert_eq!(ids(&q.paginate(&SPEC, hosts(8), key).unwrap()), vec![5]);
+    }
+
+    #[test]
+    fn test_unsupported_fields_are_rejected() {
+        let q = ListQuery::parse("filter[id]=1").unwrap();
+        assert!(q.paginate(&SPEC, hosts(3), key).unwrap_err().0.contains("not filterable"));
+        let q = ListQuery::parse("fields=score").unwrap();
+        assert!(q.paginate(&SPEC, hosts(3), key).unwrap_err().0.contains("not selectable"));
+    }
+}
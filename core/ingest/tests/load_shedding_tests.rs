@@ -0,0 +1,158 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/load_shedding_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for ingest load shedding - event priorities, shedding thresholds with hysteresis, the slow-agent rule, relaxed-durability acceptance and Retry-After on 503
+
+/*
+ * Load Shedding Tests
+ *
+ * Critical events are admitted whatever the load; low-priority events are shed once latency or
+ * queue fill crosses its threshold and until both fall below half of it; an agent with a
+ * backed-up shard is shed for everything but critical events; wal mode accepts shed events with
+ * relaxed durability instead of refusing them; every 503 of the ingest routes carries Retry-After.
+ */
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use axum::body::Body;
+    use axum::http::{header, Request, StatusCode};
+    use axum::routing::get;
+    use axum::{middleware, Router};
+    use tower::Service;
+
+    use ingest::load_shedding::{self, Admission, LoadShedConfig, LoadShedMode, LoadShedder, ShedPriority};
+    use ingest::pipeline::PipelineLoad;
+    use ingest::storage::sqlite::SqliteStore;
+    use ingest::storage::{TelemetrySource, TelemetryStore};
+
+    fn shedder(mode: LoadShedMode) -> LoadShedder {
+        LoadShedder::new(LoadShedConfig { mode, ..LoadShedConfig::off() })
+    }
+
+    /// Load with `latency_ms` average and `shard_queued` of 10 on the agent's shard (4 shards).
+    fn load(latency_ms: u64, shard_queued: usize) -> PipelineLoad {
+        PipelineLoad {
+            shard_queued,
+            queued: shard_queued,
+            queue_depth: 10,
+            shards: 4,
+            latency: Duration::from_millis(latency_ms),
+            shard_latency: Duration::from_millis(latency_ms),
+        }
+    }
+
+    #[test]
+    fn test_event_priorities() {
+        let linux = |category, event_type| ShedPriority::classify(TelemetrySource::LinuxAgent, category, event_type);
+        assert_eq!(linux(Some("detection"), None), ShedPriority::Critical);
+        assert_eq!(linux(Some("canary"), None), ShedPriority::Critical);
+        assert_eq!(linux(Some("yara_match"), None), ShedPriority::Critical);
+        assert_eq!(linux(Some("filesystem"), Some("MassWrite")), ShedPriority::Critical);
+        assert_eq!(linux(Some("process"), Some("Exec")), ShedPriority::Standard);
+        assert_eq!(linux(Some("filesystem"), Some("Write")), ShedPriority::Low);
+        assert_eq!(linux(Some("agent_stats"), None), ShedPriority::Low);
+        assert_eq!(linux(None, None), ShedPriority::Low);
+        assert_eq!(ShedPriority::classify(TelemetrySource::DpiProbe, None, None), ShedPriority::Low);
+    }
+
+    #[test]
+    fn test_off_never_sheds() {
+        let shedder = shedder(LoadShedMode::Off);
+        assert_eq!(shedder.admit(ShedPriority::Low, &load(5_000, 10)), Admission::Admit);
+        assert!(!shedder.is_shedding());
+        assert_eq!(shedder.stats().episodes, 0);
+    }
+
+    #[test]
+    fn test_low_priority_shed_while_slow_with_hysteresis() {
+        let shedder = shedder(LoadShedMode::Reject);
+        assert_eq!(shedder.admit(ShedPriority::Low, &load(100, 1)), Admission::Admit);
+        assert!(!shedder.is_shedding());
+
+        // Latency at the threshold: low priority refused, the rest keeps flowing
+        assert_eq!(shedder.admit(ShedPriority::Low, &load(250, 1)), Admission::Reject);
+        assert_eq!(shedder.admit(ShedPriority::Standard, &load(250, 1)), Admission::Admit);
+        assert_eq!(shedder.admit(ShedPriority::Critical, &load(250, 1)), Admission::Admit);
+        assert!(shedder.is_shedding());
+        assert!(shedder.stats().shedding_since.is_some());
+
+        // Below the threshold but above half of it: still shedding
+        assert_eq!(shedder.admit(ShedPriority::Low, &load(150, 1)), Admission::Reject);
+        // Below half: stopped
+        assert_eq!(shedder.admit(ShedPriority::Low, &load(120, 1)), Admission::Admit);
+        assert!(!shedder.is_shedding());
+
+        let stats = shedder.stats();
+        assert_eq!((stats.episodes, stats.rejected_low_priority, stats.rejected_slow_agent), (1, 2, 0));
+        assert_eq!(stats.shedding_since, None);
+    }
+
+    #[test]
+    fn test_queue_fill_starts_shedding() {
+        let shedder = shedder(LoadShedMode::Reject);
+        // 20 of 40 queued across the pipeline, the agent's own shard below the slow-agent mark
+        let busy = PipelineLoad { queued: 20, ..load(10, 2) };
+        assert_eq!(shedder.admit(ShedPriority::Low, &busy), Admission::Reject);
+        assert_eq!(shedder.admit(ShedPriority::Standard, &busy), Admission::Admit);
+        let draining = PipelineLoad { queued: 11, ..load(10, 2) };
+        assert_eq!(shedder.admit(ShedPriority::Low, &draining), Admission::Reject);
+        let idle = PipelineLoad { queued: 4, ..load(10, 2) };
+        assert_eq!(shedder.admit(ShedPriority::Low, &idle), Admission::Admit);
+    }
+
+    #[test]
+    fn test_slow_agent_shed_except_critical() {
+        let shedder = shedder(LoadShedMode::Reject);
+        // Half the agent's shard queue: slow agent, though the pipeline is not shedding
+        let slow = PipelineLoad { queued: 5, ..load(10, 5) };
+        assert_eq!(shedder.admit(ShedPriority::Standard, &slow), Admission::Reject);
+        assert_eq!(shedder.admit(ShedPriority::Low, &slow), Admission::Reject);
+        assert_eq!(shedder.admit(ShedPriority::Critical, &slow), Admission::Admit);
+        assert!(!shedder.is_shedding());
+        assert_eq!(shedder.stats().rejected_slow_agent, 2);
+    }
+
+    #[test]
+    fn test_wal_mode_relaxes_instead_of_refusing() {
+        let shedder = shedder(LoadShedMode::Wal);
+        assert_eq!(shedder.admit(ShedPriority::Low, &load(400, 1)), Admission::Relaxed);
+        assert_eq!(shedder.admit(ShedPriority::Standard, &load(400, 1)), Admission::Admit);
+        assert_eq!(shedder.admit(ShedPriority::Standard, &load(400, 7)), Admission::Relaxed);
+        assert_eq!(shedder.admit(ShedPriority::Critical, &load(400, 7)), Admission::Admit);
+
+        let stats = shedder.stats();
+        assert_eq!(stats.mode, "wal");
+        assert_eq!((stats.relaxed_low_priority, stats.relaxed_slow_agent), (1, 1));
+        assert_eq!((stats.rejected_low_priority, stats.rejected_slow_agent), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_relaxed_durability_commits() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let mut tx = store.begin().await.unwrap();
+        tx.relax_durability().await.unwrap();
+        tx.commit().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_service_unavailable_carries_retry_after() {
+        let shedder = Arc::new(LoadShedder::new(LoadShedConfig {
+            retry_after_secs: 7,
+            ..LoadShedConfig::off()
+        }));
+        let mut app = Router::new()
+            .route("/ingest/busy", get(|| async { StatusCode::SERVICE_UNAVAILABLE }))
+            .route("/ingest/ok", get(|| async { StatusCode::OK }))
+            .route_layer(middleware::from_fn_with_state(shedder, load_shedding::retry_after));
+
+        let busy = app.call(Request::get("/ingest/busy").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(busy.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(busy.headers()[header::RETRY_AFTER], "7");
+
+        let ok = app.call(Request::get("/ingest/ok").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(ok.status(), StatusCode::OK);
+        assert!(ok.headers().get(header::RETRY_AFTER).is_none());
+    }
+}
@@ -0,0 +1,148 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/maintenance_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for maintenance windows - window validation, detection class and downgrade matching, scope coverage and the choice between overlapping windows
+
+/*
+ * Maintenance Window Tests
+ *
+ * Windows are checked before they are opened (bounded range and lead time, downgrade target,
+ * classes, reason), apply only to matching classes and never raise a severity, cover a
+ * detection by host, zone or tenant, and a suppressing window wins over a downgrading one.
+ */
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+    use serde_json::json;
+    use uuid::Uuid;
+
+    use ingest::maintenance::{
+        self, DetectionClass, DetectionScope, MaintenanceAction, MaintenanceScope, MaintenanceWindowSpec, ScopeKind,
+        MAX_LEAD_DAYS, MAX_WINDOW_HOURS,
+    };
+    use ingest::storage::DetectionRecord;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 17, 21, 0, 0).unwrap()
+    }
+
+    fn class(engine: &str) -> DetectionClass {
+        DetectionClass { detection_engine: Some(engine.to_string()), ..Default::default() }
+    }
+
+    fn window(kind: ScopeKind, value: &str, action: MaintenanceAction) -> MaintenanceWindowSpec {
+        MaintenanceWindowSpec {
+            scope: MaintenanceScope { kind, value: value.to_string() },
+            starts_at: now() + Duration::hours(1),
+            ends_at: now() + Duration::hours(5),
+            action,
+            downgrade_to: (action == MaintenanceAction::Downgrade).then(|| "info".to_string()),
+            classes: vec![class("ingest_key_pinning")],
+            reason: "October patch window (CHG-2291)".to_string(),
+            created_by: "ops-lead".to_string(),
+        }
+    }
+
+    fn detection(engine: &str, severity: &str) -> DetectionRecord {
+        DetectionRecord {
+            detection_engine: engine.to_string(),
+            detection_name: "agent_unexpected_signing_key".to_string(),
+            detection_category: Some("identity_spoofing".to_string()),
+            severity: severity.to_string(),
+            confidence: 1.0,
+            reasoning: "Agent presented an unpinned signing key".to_string(),
+            artifacts: json!({ "component_id": "ws-0142", "agent_id": Uuid::nil().to_string() }),
+            deterministic_key: vec![0u8; 32],
+        }
+    }
+
+    #[test]
+    fn test_windows_are_validated() {
+        let ok = window(ScopeKind::Zone, "hq-servers", MaintenanceAction::Downgrade);
+        assert!(ok.validate(now()).is_ok());
+        // Already running is fine as long as it has not ended
+        assert!(MaintenanceWindowSpec { starts_at: now() - Duration::hours(1), ..ok.clone() }.validate(now()).is_ok());
+
+        let refused = [
+            MaintenanceWindowSpec { ends_at: ok.starts_at, ..ok.clone() },
+            MaintenanceWindowSpec { starts_at: now() - Duration::hours(3), ends_at: now(), ..ok.clone() },
+            MaintenanceWindowSpec { ends_at: ok.starts_at + Duration::hours(MAX_WINDOW_HOURS + 1), ..ok.clone() },
+            MaintenanceWindowSpec {
+                starts_at: now() + Duration::days(MAX_LEAD_DAYS + 1),
+                ends_at: now() + Duration::days(MAX_LEAD_DAYS + 1) + Duration::hours(1),
+                ..ok.clone()
+            },
+            MaintenanceWindowSpec { downgrade_to: None, ..ok.clone() },
+            MaintenanceWindowSpec { downgrade_to: Some("low".to_string()), ..ok.clone() },
+            MaintenanceWindowSpec { action: MaintenanceAction::Suppress, ..ok.clone() },
+            MaintenanceWindowSpec { classes: Vec::new(), ..ok.clone() },
+            MaintenanceWindowSpec { classes: vec![DetectionClass::default()], ..ok.clone() },
+            MaintenanceWindowSpec { reason: " ".to_string(), ..ok.clone() },
+            MaintenanceWindowSpec { scope: MaintenanceScope { kind: ScopeKind::Host, value: String::new() }, ..ok.clone() },
+        ];
+        for spec in refused {
+            assert!(spec.validate(now()).is_err(), "accepted {:?}", spec);
+        }
+
+        let unknown = json!({
+            "scope": { "kind": "site", "value": "hq" },
+            "starts_at": now().to_rfc3339(), "ends_at": (now() + Duration::hours(1)).to_rfc3339(),
+            "action": "suppress", "classes": [{ "detection_engine": "x" }], "reason": "r", "created_by": "c",
+        });
+        assert!(serde_json::from_value::<MaintenanceWindowSpec>(unknown).is_err());
+    }
+
+    #[test]
+    fn test_classes_and_downgrades_match() {
+        let downgrade = window(ScopeKind::Host, "ws-0142", MaintenanceAction::Downgrade);
+        assert!(downgrade.matches(&detection("ingest_key_pinning", "critical")));
+        assert!(!downgrade.matches(&detection("ingest_rate_budget", "critical")));
+        // A window never raises a severity, and leaves detections already at the target alone
+        assert!(!downgrade.matches(&detection("ingest_key_pinning", "info")));
+        assert!(!downgrade.matches(&detection("ingest_key_pinning", "debug")));
+
+        let suppress = window(ScopeKind::Host, "ws-0142", MaintenanceAction::Suppress);
+        assert!(suppress.matches(&detection("ingest_key_pinning", "debug")));
+
+        let by_category = DetectionClass { detection_category: Some("identity_spoofing".to_string()), ..Default::default() };
+        assert!(by_category.matches(&detection("ingest_identity_conflict", "error")));
+        let narrowed = DetectionClass { detection_name: Some("other".to_string()), ..by_category };
+        assert!(!narrowed.matches(&detection("ingest_identity_conflict", "error")));
+    }
+
+    #[test]
+    fn test_scope_covers_hosts_zones_and_tenants() {
+        let mut scope = DetectionScope::from_artifacts(&json!({
+            "component_id": "ws-0142",
+            "source_zone": "hq-office",
+            "destination_zone": "hq-servers",
+        }));
+        let covers = |scope: &DetectionScope, kind, value: &str| scope.covers(&MaintenanceScope { kind, value: value.to_string() });
+        assert!(covers(&scope, ScopeKind::Host, "ws-0142"));
+        assert!(!covers(&scope, ScopeKind::Host, "ws-0143"));
+        assert!(covers(&scope, ScopeKind::Zone, "hq-servers"));
+        assert!(covers(&scope, ScopeKind::Zone, "hq-office"));
+        assert!(!covers(&scope, ScopeKind::Tenant, "acme"));
+        scope.tenant = Some("acme".to_string());
+        assert!(covers(&scope, ScopeKind::Tenant, "acme"));
+
+        assert_eq!(DetectionScope::from_artifacts(&json!({ "component_id": 7 })), DetectionScope::default());
+    }
+
+    #[test]
+    fn test_suppressing_window_wins_over_downgrade() {
+        let scope = DetectionScope::from_artifacts(&json!({ "component_id": "ws-0142", "zone": "hq-office" }));
+        let downgrade = (Uuid::new_v4(), window(ScopeKind::Zone, "hq-office", MaintenanceAction::Downgrade));
+        let suppress = (Uuid::new_v4(), window(ScopeKind::Host, "ws-0142", MaintenanceAction::Suppress));
+        let elsewhere = (Uuid::new_v4(), window(ScopeKind::Host, "ws-0143", MaintenanceAction::Suppress));
+
+        let critical = detection("ingest_key_pinning", "critical");
+        let windows = vec![downgrade.clone(), elsewhere.clone(), suppress.clone()];
+        assert_eq!(maintenance::select(&windows, &critical, &scope).map(|w| w.0), Some(suppress.0));
+        let windows = vec![downgrade.clone(), elsewhere.clone()];
+        assert_eq!(maintenance::select(&windows, &critical, &scope).map(|w| w.0), Some(downgrade.0));
+        // Out of scope or out of class
+        assert!(maintenance::select(&[elsewhere], &critical, &scope).is_none());
+        assert!(maintenance::select(&windows, &detection("ingest_rate_budget", "critical"), &scope).is_none());
+    }
+}
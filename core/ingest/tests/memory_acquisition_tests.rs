@@ -0,0 +1,97 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/memory_acquisition_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for memory acquisition - the operator request policy gate (scope, pid, two-person rule) and the chunk spool with per-chunk and whole-image SHA-256 verification
+
+/*
+ * Memory Acquisition Tests
+ *
+ * A request passes only with an allowed scope, a pid exactly for process scope and an approver
+ * other than the requester; spooled chunks are re-verified one by one and as a whole image,
+ * so a chunk changed or lost on disk is caught before the manifest is written.
+ */
+
+#[cfg(test)]
+mod tests {
+    use crypto::digest::Sha256;
+    use ingest::memory_acquisition::{
+        self, AcquisitionRequest, AcquisitionScope, ChunkEntry, MemoryAcquisitionPolicy,
+    };
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+    use uuid::Uuid;
+
+    fn policy(scopes: Vec<AcquisitionScope>) -> MemoryAcquisitionPolicy {
+        MemoryAcquisitionPolicy {
+            scopes,
+            max_bytes: 1 << 30,
+            chunk_bytes: 1 << 20,
+            spool_dir: PathBuf::from("/nonexistent"),
+        }
+    }
+
+    fn request(scope: AcquisitionScope, pid: Option<i32>) -> AcquisitionRequest {
+        AcquisitionRequest {
+            agent_id: Uuid::new_v4(),
+            scope,
+            pid,
+            incident_id: None,
+            reason: "INC-42 suspected injected loader".to_string(),
+            requested_by: "analyst.one".to_string(),
+            approved_by: "lead.two".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_policy_gate() {
+        let process_only = policy(vec![AcquisitionScope::Process]);
+        assert!(process_only.check(&request(AcquisitionScope::Process, Some(4242))).is_ok());
+        assert!(process_only.check(&request(AcquisitionScope::Full, None)).is_err(), "scope not allowed");
+        assert!(process_only.check(&request(AcquisitionScope::Process, None)).is_err(), "process needs a pid");
+        assert!(process_only.check(&request(AcquisitionScope::Process, Some(0))).is_err());
+
+        let both = policy(vec![AcquisitionScope::Process, AcquisitionScope::Full]);
+        assert!(both.check(&request(AcquisitionScope::Full, None)).is_ok());
+        assert!(both.check(&request(AcquisitionScope::Full, Some(1))).is_err(), "full takes no pid");
+
+        // Two-person rule
+        let mut req = request(AcquisitionScope::Full, None);
+        req.approved_by = " Analyst.One ".to_string();
+        assert!(both.check(&req).is_err());
+        req.approved_by = String::new();
+        assert!(both.check(&req).is_err());
+        let mut req = request(AcquisitionScope::Full, None);
+        req.reason = "  ".to_string();
+        assert!(both.check(&req).is_err());
+
+        assert_eq!(serde_json::to_value(AcquisitionScope::Full).unwrap(), "full");
+        assert_eq!(AcquisitionScope::parse("process"), Some(AcquisitionScope::Process));
+        assert_eq!(AcquisitionScope::parse("kernel"), None);
+    }
+
+    #[test]
+    fn test_spooled_chunks_are_verified() {
+        let spool = TempDir::new().unwrap();
+        let id = Uuid::new_v4();
+        let parts: [&[u8]; 3] = [b"first chunk ", b"second chunk ", b"tail"];
+        let mut chunks = Vec::new();
+        for (i, part) in parts.iter().enumerate() {
+            memory_acquisition::write_chunk(spool.path(), id, i as u32, part).unwrap();
+            chunks.push(ChunkEntry { index: i as u32, sha256: hex::encode(Sha256::digest(part)), bytes: part.len() as u64 });
+        }
+        let (sha256, bytes) = memory_acquisition::verify_chunks(spool.path(), id, &chunks).unwrap();
+        assert_eq!(sha256, hex::encode(Sha256::digest(parts.concat())));
+        assert_eq!(bytes, parts.concat().len() as u64);
+
+        // A chunk missing from the sequence
+        assert!(memory_acquisition::verify_chunks(spool.path(), id, &chunks[1..]).is_err());
+
+        // A chunk changed on disk after it was recorded
+        let chunk_file = memory_acquisition::chunk_dir(spool.path(), id).join(memory_acquisition::chunk_file_name(1));
+        std::fs::write(&chunk_file, b"SECOND chunk ").unwrap();
+        let err = memory_acquisition::verify_chunks(spool.path(), id, &chunks).unwrap_err();
+        assert!(err.contains("chunk 1"), "{}", err);
+
+        memory_acquisition::remove_spool(spool.path(), id);
+        assert!(!memory_acquisition::chunk_dir(spool.path(), id).exists());
+    }
+}
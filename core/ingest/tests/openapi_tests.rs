@@ -0,0 +1,130 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/openapi_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for the generated ingest OpenAPI contract - version, route coverage and security schemes
+
+/*
+ * OpenAPI Contract Tests
+ *
+ * Every route mounted by HttpIngestionServer must appear in the generated document with its
+ * authentication scheme, so integrators never see an undocumented endpoint.
+ */
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value as JsonValue;
+
+    use ingest::openapi;
+
+    fn contract() -> JsonValue {
+        serde_json::to_value(openapi::openapi()).unwrap()
+    }
+
+    #[test]
+    fn test_contract_is_openapi_3_1() {
+        let doc = contract();
+        assert!(doc["openapi"].as_str().unwrap().starts_with("3.1"));
+        assert_eq!(doc["info"]["title"], "RansomEye Ingest API");
+    }
+
+    #[test]
+    fn test_every_route_is_documented_with_security() {
+        let doc = contract();
+        let routes = [
+            ("/ingest/linux", "post", "agent_token"),
+            ("/ingest/dpi", "post", "agent_token"),
+            ("/agents/enroll", "post", "enrollment_key"),
+            ("/agents/token/rotate", "post", "agent_token"),
+            ("/agents/token/revoke", "post", "enrollment_key"),
+            ("/agents/key/rotate", "post", "enrollment_key"),
+            ("/admin/runtime-config", "get", "admin_key"),
+            ("/admin/runtime-config", "post", "admin_key"),
+            ("/admin/orchestrator-link", "get", "admin_key"),
+            ("/admin/pipeline", "get", "admin_key"),
+            ("/admin/health", "get", "admin_key"),
+            ("/admin/load-shedding", "get", "admin_key"),
+            ("/admin/drops", "get", "admin_key"),
+            ("/admin/drops/ingest", "get", "admin_key"),
+            ("/admin/export/events", "get", "admin_key"),
+            ("/admin/export/stats", "get", "admin_key"),
+            ("/admin/fleet/inventory", "get", "admin_key"),
+            ("/admin/dashboard/detections-by-severity", "get", "admin_key"),
+            ("/admin/dashboard/events-per-minute", "get", "admin_key"),
+            ("/admin/dashboard/fleet-summary", "get", "admin_key"),
+            ("/admin/dashboard/cache", "get", "admin_key"),
+            ("/admin/identity-conflicts", "get", "admin_key"),
+            ("/admin/identity-conflicts/resolve", "post", "admin_key"),
+            ("/admin/webhooks", "get", "admin_key"),
+            ("/admin/webhooks", "post", "admin_key"),
+            ("/admin/webhooks/disable", "post", "admin_key"),
+            ("/admin/webhooks/deliveries", "get", "admin_key"),
+            ("/admin/webhooks/deliveries/redrive", "post", "admin_key"),
+            ("/admin/legal-holds", "get", "admin_key"),
+            ("/admin/legal-holds", "post", "admin_key"),
+            ("/admin/legal-holds/release", "post", "admin_key"),
+            ("/admin/suppressions", "get", "admin_key"),
+            ("/admin/suppressions", "post", "admin_key"),
+            ("/admin/suppressions/expire", "post", "admin_key"),
+            ("/admin/maintenance-windows", "get", "admin_key"),
+            ("/admin/maintenance-windows", "post", "admin_key"),
+            ("/admin/maintenance-windows/cancel", "post", "admin_key"),
+            ("/admin/maintenance-windows/report", "get", "admin_key"),
+            ("/admin/feature-flags", "get", "admin_key"),
+            ("/admin/feature-flags", "post", "admin_key"),
+            ("/admin/feature-flags/clear", "post", "admin_key"),
+            ("/admin/annotations", "get", "admin_key"),
+            ("/admin/annotations", "post", "admin_key"),
+            ("/admin/annotations/edit", "post", "admin_key"),
+            ("/admin/audit-reviews", "get", "admin_key"),
+            ("/admin/audit-reviews", "post", "admin_key"),
+            ("/admin/audit-reviews/coverage", "get", "admin_key"),
+            ("/admin/incidents/close", "post", "admin_key"),
+            ("/admin/rules/metrics", "get", "admin_key"),
+            ("/admin/rules/tuning", "get", "admin_key"),
+            ("/admin/retention/runs", "get", "admin_key"),
+            ("/admin/retention/status", "get", "admin_key"),
+            ("/admin/pcap-captures", "get", "admin_key"),
+            ("/probes/captures/claim", "post", "agent_token"),
+            ("/probes/captures/upload", "post", "agent_token"),
+            ("/probes/captures/fail", "post", "agent_token"),
+            ("/admin/memory-acquisitions", "get", "admin_key"),
+            ("/admin/memory-acquisitions", "post", "admin_key"),
+            ("/agents/memory-acquisitions/claim", "post", "agent_token"),
+            ("/agents/memory-acquisitions/chunk", "post", "agent_token"),
+            ("/agents/memory-acquisitions/complete", "post", "agent_token"),
+            ("/agents/memory-acquisitions/fail", "post", "agent_token"),
+            ("/agents/log-segments", "post", "agent_token"),
+            ("/agents/sandbox/samples", "post", "agent_token"),
+            ("/admin/sandbox-submissions", "get", "admin_key"),
+            ("/admin/sandbox-submissions", "post", "admin_key"),
+            ("/admin/yara-rule-packs", "get", "admin_key"),
+            ("/admin/yara-rule-packs", "post", "admin_key"),
+            ("/admin/yara-scans", "get", "admin_key"),
+            ("/admin/yara-scans", "post", "admin_key"),
+            ("/agents/yara/poll", "post", "agent_token"),
+            ("/schema", "get", "admin_key"),
+            ("/auth/session", "get", "operator_bearer"),
+        ];
+        for (path, method, scheme) in routes {
+            let op = &doc["paths"][path][method];
+            assert!(op.is_object(), "{} {} missing from contract", method, path);
+            assert!(op["security"][0].get(scheme).is_some(), "{} {} lacks {}", method, path, scheme);
+            assert!(doc["components"]["securitySchemes"].get(scheme).is_some());
+        }
+        // Sign-in endpoints are the way to obtain credentials and carry none
+        for (path, method) in [("/auth/oidc/login", "get"), ("/auth/oidc/callback", "get"), ("/auth/break-glass", "post")] {
+            let op = &doc["paths"][path][method];
+            assert!(op.is_object(), "{} {} missing from contract", method, path);
+            assert!(op.get("security").is_none(), "{} {} should not require credentials", method, path);
+        }
+        assert_eq!(doc["paths"].as_object().unwrap().len(), 64);
+    }
+
+    #[test]
+    fn test_list_envelope_schema() {
+        let doc = contract();
+        let page = &doc["components"]["schemas"]["Page"]["properties"];
+        for field in ["data", "next_cursor", "total_estimate"] {
+            assert!(page.get(field).is_some(), "Page lacks {}", field);
+        }
+    }
+}
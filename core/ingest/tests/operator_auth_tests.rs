@@ -0,0 +1,330 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/operator_auth_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for operator authentication - role permissions and group mapping, RS256 token validation against a JWKS, signed sessions, break-glass accounts with lockout, and the admin route middleware
+
+/*
+ * Operator Authentication Tests
+ *
+ * Tokens are signed with a fixture RSA key published in a fixture JWKS; the provider is built
+ * with discovery and keys preloaded, so no identity provider is contacted. The middleware is
+ * exercised on a small router whose handlers run the admin key check like the real ones.
+ */
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use axum::body::Body;
+    use axum::extract::State;
+    use axum::http::{header, Method, Request, StatusCode};
+    use axum::routing::get;
+    use axum::{middleware, Router};
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+    use chrono::Utc;
+    use ring::rand::SystemRandom;
+    use ring::signature::{RsaKeyPair, RSA_PKCS1_SHA256};
+    use serde_json::{json, Value as JsonValue};
+    use tower::Service;
+    use uuid::Uuid;
+
+    use ingest::http_runtime_admin::AdminKey;
+    use ingest::oidc::{Jwks, OidcConfig, OidcError, OidcProvider, ProviderMetadata};
+    use ingest::operator_auth::{
+        self, BreakGlass, BreakGlassAccount, OperatorAuth, OperatorAuthError, OperatorAuthMethod, OperatorGate,
+        OperatorIdentity, OperatorRole, RoleMap, SessionSigner, GRANT_HEADER, MAX_BREAK_GLASS_FAILURES,
+        MIN_PBKDF2_ITERATIONS,
+    };
+    use ingest::storage::SqliteStore;
+
+    const ISSUER: &str = "https://idp.example.com/realms/soc";
+    const ADMIN_KEY: &str = "0123456789abcdef-admin";
+
+    fn provider() -> OidcProvider {
+        let config = OidcConfig {
+            issuer: ISSUER.to_string(),
+            client_id: "ransomeye-dashboard".to_string(),
+            client_secret: None,
+            redirect_url: None,
+            audience: "ransomeye-api".to_string(),
+            groups_claim: "realm_access.groups".to_string(),
+            scopes: "openid".to_string(),
+        };
+        let metadata = ProviderMetadata {
+            issuer: ISSUER.to_string(),
+            authorization_endpoint: format!("{}/protocol/openid-connect/auth", ISSUER),
+            token_endpoint: format!("{}/protocol/openid-connect/token", ISSUER),
+            jwks_uri: format!("{}/protocol/openid-connect/certs", ISSUER),
+        };
+        let jwks: Jwks = serde_json::from_str(include_str!("fixtures/oidc_test_jwks.json")).unwrap();
+        OidcProvider::with_keys(config, metadata, &jwks).unwrap()
+    }
+
+    fn sign_jwt(header: JsonValue, claims: JsonValue) -> String {
+        let key = RsaKeyPair::from_pkcs8(include_bytes!("fixtures/oidc_test_rs256.pk8")).unwrap();
+        let input = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        );
+        let mut sig = vec![0u8; key.public().modulus_len()];
+        key.sign(&RSA_PKCS1_SHA256, &SystemRandom::new(), input.as_bytes(), &mut sig).unwrap();
+        format!("{}.{}", input, URL_SAFE_NO_PAD.encode(sig))
+    }
+
+    fn rs256() -> JsonValue {
+        json!({ "alg": "RS256", "kid": "test-key-1", "typ": "JWT" })
+    }
+
+    fn claims(aud: &str, exp_in: i64, groups: &[&str]) -> JsonValue {
+        let now = Utc::now().timestamp();
+        json!({
+            "iss": ISSUER,
+            "aud": [aud, "account"],
+            "sub": "8f2d1c",
+            "preferred_username": "alice",
+            "iat": now,
+            "exp": now + exp_in,
+            "realm_access": { "groups": groups },
+        })
+    }
+
+    fn signer() -> SessionSigner {
+        SessionSigner::new(&[7u8; 32]).unwrap()
+    }
+
+    fn session(role: OperatorRole, method: OperatorAuthMethod, expires_in: i64) -> OperatorIdentity {
+        OperatorIdentity {
+            subject: "alice".to_string(),
+            name: "alice".to_string(),
+            role,
+            method,
+            expires_at: Utc::now() + chrono::Duration::seconds(expires_in),
+            session_id: Some(Uuid::new_v4()),
+            reason: (method == OperatorAuthMethod::BreakGlass).then(|| "IdP outage INC-4711".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_role_permissions() {
+        let viewer = OperatorRole::Viewer;
+        assert!(viewer.permits(&Method::GET, "/admin/feature-flags"));
+        assert!(!viewer.permits(&Method::POST, "/admin/annotations"));
+
+        let analyst = OperatorRole::Analyst;
+        assert!(analyst.permits(&Method::POST, "/admin/annotations"));
+        assert!(analyst.permits(&Method::POST, "/admin/yara-scans"));
+        assert!(analyst.permits(&Method::POST, "/admin/incidents/close"));
+        assert!(!analyst.permits(&Method::POST, "/admin/feature-flags"));
+        assert!(!analyst.permits(&Method::POST, "/admin/runtime-config"));
+
+        assert!(OperatorRole::Admin.permits(&Method::POST, "/admin/feature-flags"));
+    }
+
+    #[test]
+    fn test_role_map_takes_highest_role() {
+        let map = RoleMap::parse("soc-viewers=viewer, soc-analysts=analyst").unwrap();
+        assert_eq!(map.role_for(["soc-viewers", "soc-analysts"]), Some(OperatorRole::Analyst));
+        assert_eq!(map.role_for(["other"]), None);
+        assert!(RoleMap::parse("soc=superuser").is_err());
+        assert!(RoleMap::parse("soc").is_err());
+        assert!(RoleMap::parse("").unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_access_token_validation() {
+        let provider = provider();
+        let now = Utc::now();
+
+        let valid = sign_jwt(rs256(), claims("ransomeye-api", 300, &["soc-analysts"]));
+        let token = provider.validate_access_token(&valid, now).await.unwrap();
+        assert_eq!((token.subject.as_str(), token.name.as_str()), ("8f2d1c", "alice"));
+        assert_eq!(token.groups, vec!["soc-analysts".to_string()]);
+
+        // ID tokens are for the client, not the API
+        let wrong_aud = sign_jwt(rs256(), claims("ransomeye-dashboard", 300, &[]));
+        assert!(matches!(provider.validate_access_token(&wrong_aud, now).await, Err(OidcError::InvalidClaim(_))));
+
+        let mut other_issuer = claims("ransomeye-api", 300, &[]);
+        other_issuer["iss"] = json!("https://evil.example.com");
+        let other_issuer = sign_jwt(rs256(), other_issuer);
+        assert!(matches!(provider.validate_access_token(&other_issuer, now).await, Err(OidcError::InvalidClaim(_))));
+
+        // 60s of clock leeway
+        let just_expired = sign_jwt(rs256(), claims("ransomeye-api", -30, &[]));
+        assert!(provider.validate_access_token(&just_expired, now).await.is_ok());
+        let expired = sign_jwt(rs256(), claims("ransomeye-api", -120, &[]));
+        assert!(matches!(provider.validate_access_token(&expired, now).await, Err(OidcError::Expired(_))));
+
+        let mut future = claims("ransomeye-api", 900, &[]);
+        future["nbf"] = json!(now.timestamp() + 600);
+        let future = sign_jwt(rs256(), future);
+        assert!(matches!(provider.validate_access_token(&future, now).await, Err(OidcError::NotYetValid(_))));
+    }
+
+    #[tokio::test]
+    async fn test_forged_tokens_rejected() {
+        let provider = provider();
+        let now = Utc::now();
+        let valid = sign_jwt(rs256(), claims("ransomeye-api", 300, &["soc-viewers"]));
+        let parts: Vec<&str> = valid.split('.').collect();
+
+        // Escalated groups under the original signature
+        let escalated = URL_SAFE_NO_PAD.encode(claims("ransomeye-api", 300, &["soc-admins"]).to_string());
+        let tampered = format!("{}.{}.{}", parts[0], escalated, parts[2]);
+        assert_eq!(provider.validate_access_token(&tampered, now).await, Err(OidcError::BadSignature));
+
+        let none = format!("{}.{}.", URL_SAFE_NO_PAD.encode(json!({ "alg": "none" }).to_string()), parts[1]);
+        assert!(matches!(provider.validate_access_token(&none, now).await, Err(OidcError::UnsupportedAlgorithm(_))));
+        let hs256 = format!("{}.{}.{}", URL_SAFE_NO_PAD.encode(json!({ "alg": "HS256" }).to_string()), parts[1], parts[2]);
+        assert!(matches!(provider.validate_access_token(&hs256, now).await, Err(OidcError::UnsupportedAlgorithm(_))));
+
+        // Unknown kid: the JWKS was just loaded, so no refetch is attempted
+        let rotated = sign_jwt(json!({ "alg": "RS256", "kid": "unknown" }), claims("ransomeye-api", 300, &[]));
+        assert_eq!(
+            provider.validate_access_token(&rotated, now).await,
+            Err(OidcError::UnknownKey("unknown".to_string()))
+        );
+        assert!(matches!(provider.validate_access_token("not-a-jwt", now).await, Err(OidcError::Malformed(_))));
+    }
+
+    #[tokio::test]
+    async fn test_id_token_nonce_and_role_mapping() {
+        let provider = provider();
+        let now = Utc::now();
+        let mut id_claims = claims("ransomeye-dashboard", 300, &["soc-viewers", "soc-admins"]);
+        id_claims["nonce"] = json!("n-123");
+        let id_token = sign_jwt(rs256(), id_claims);
+        assert!(matches!(provider.validate_id_token(&id_token, "n-other", now).await, Err(OidcError::InvalidClaim(_))));
+        let verified = provider.validate_id_token(&id_token, "n-123", now).await.unwrap();
+
+        let auth = OperatorAuth::new(
+            Some(provider),
+            RoleMap::parse("soc-viewers=viewer,soc-admins=admin").unwrap(),
+            None,
+            Some(signer()),
+            Duration::from_secs(3600),
+            None,
+        )
+        .unwrap();
+        let session_id = Uuid::new_v4();
+        let identity = auth.identity_for(&verified, now, Some(session_id)).unwrap();
+        assert_eq!((identity.role, identity.method), (OperatorRole::Admin, OperatorAuthMethod::Oidc));
+        assert_eq!(identity.expires_at, now + chrono::Duration::hours(1));
+
+        // Bearer access tokens resolve per request; a token without a mapped group is refused
+        let unmapped = sign_jwt(rs256(), claims("ransomeye-api", 300, &["finance"]));
+        let err = auth.authenticate(&unmapped, now).await.unwrap_err();
+        assert_eq!(err.status(), StatusCode::FORBIDDEN);
+        let viewer = sign_jwt(rs256(), claims("ransomeye-api", 300, &["soc-viewers"]));
+        assert_eq!(auth.authenticate(&viewer, now).await.unwrap().role, OperatorRole::Viewer);
+    }
+
+    #[test]
+    fn test_session_round_trip() {
+        let signer = signer();
+        let identity = session(OperatorRole::Analyst, OperatorAuthMethod::Oidc, 300);
+        let token = signer.issue(&identity);
+        assert!(operator_auth::is_session_token(&token));
+        assert_eq!(signer.verify(&token, Utc::now()).unwrap(), identity);
+
+        assert!(matches!(
+            signer.verify(&token, Utc::now() + chrono::Duration::seconds(301)),
+            Err(OperatorAuthError::Expired(_))
+        ));
+        let other = SessionSigner::new(&[8u8; 32]).unwrap();
+        assert_eq!(other.verify(&token, Utc::now()), Err(OperatorAuthError::BadSignature));
+        let forged = token.replacen("res1.", "res1.e30", 1);
+        assert_eq!(signer.verify(&forged, Utc::now()), Err(OperatorAuthError::BadSignature));
+        // Login state sealed under another kind is not a session
+        assert!(matches!(signer.verify(&signer.seal("reo1", &identity), Utc::now()), Err(OperatorAuthError::Malformed(_))));
+        assert!(SessionSigner::new(b"short").is_err());
+    }
+
+    #[test]
+    fn test_break_glass_accounts_and_lockout() {
+        let stored = operator_auth::hash_password("correct horse battery", MIN_PBKDF2_ITERATIONS).unwrap();
+        let account = BreakGlassAccount::parse_line(&format!("oncall:admin:{}", stored)).unwrap();
+        assert_eq!((account.username.as_str(), account.role), ("oncall", OperatorRole::Admin));
+
+        let weak = operator_auth::hash_password("x", 1000).unwrap();
+        assert!(BreakGlassAccount::parse_line(&format!("oncall:admin:{}", weak)).unwrap_err().contains("minimum"));
+        assert!(BreakGlassAccount::parse_line(&format!("oncall:root:{}", stored)).is_err());
+        assert!(BreakGlassAccount::parse_line("oncall:admin:plaintext").is_err());
+
+        let break_glass = BreakGlass::new(vec![account], Duration::from_secs(900));
+        let t0 = Instant::now();
+        assert_eq!(break_glass.authenticate("oncall", "correct horse battery", t0), Ok(OperatorRole::Admin));
+        assert_eq!(break_glass.authenticate("nobody", "x", t0), Err(OperatorAuthError::InvalidCredentials));
+
+        for _ in 0..MAX_BREAK_GLASS_FAILURES {
+            assert_eq!(break_glass.authenticate("oncall", "wrong", t0), Err(OperatorAuthError::InvalidCredentials));
+        }
+        // Locked: even the right password is refused until the window passes
+        let locked = break_glass.authenticate("oncall", "correct horse battery", t0).unwrap_err();
+        assert_eq!(locked.status(), StatusCode::TOO_MANY_REQUESTS);
+        let later = t0 + operator_auth::BREAK_GLASS_LOCKOUT;
+        assert_eq!(break_glass.authenticate("oncall", "correct horse battery", later), Ok(OperatorRole::Admin));
+    }
+
+    async fn admin_handler(State(key): State<Arc<AdminKey>>, headers: axum::http::HeaderMap) -> StatusCode {
+        key.check(&headers).map(|_| StatusCode::OK).unwrap_or_else(|code| code)
+    }
+
+    fn router(auth: OperatorAuth, store: SqliteStore) -> Router {
+        let auth = Arc::new(auth);
+        let key = Arc::new(AdminKey::new(Some(ADMIN_KEY.as_bytes().to_vec())).with_operator_grant(auth.grant()));
+        Router::new()
+            .route("/admin/feature-flags", get(admin_handler).post(admin_handler))
+            .route_layer(middleware::from_fn_with_state(
+                OperatorGate { auth, store: Arc::new(store) },
+                operator_auth::authorize,
+            ))
+            .with_state(key)
+    }
+
+    fn request(method: Method, bearer: Option<&str>) -> Request<Body> {
+        let mut builder = Request::builder().method(method).uri("/admin/feature-flags");
+        if let Some(token) = bearer {
+            builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_middleware_enforces_role() {
+        let break_glass = BreakGlass::new(Vec::new(), Duration::from_secs(900));
+        let auth = OperatorAuth::new(None, RoleMap::default(), Some(break_glass), Some(signer()), Duration::from_secs(3600), None)
+            .unwrap();
+        let mut app = router(auth, SqliteStore::open_in_memory().unwrap());
+        let signer = signer();
+
+        let viewer = signer.issue(&session(OperatorRole::Viewer, OperatorAuthMethod::Oidc, 300));
+        assert_eq!(app.call(request(Method::GET, Some(&viewer))).await.unwrap().status(), StatusCode::OK);
+        assert_eq!(app.call(request(Method::POST, Some(&viewer))).await.unwrap().status(), StatusCode::FORBIDDEN);
+
+        let admin = signer.issue(&session(OperatorRole::Admin, OperatorAuthMethod::Oidc, 300));
+        assert_eq!(app.call(request(Method::POST, Some(&admin))).await.unwrap().status(), StatusCode::OK);
+        let expired = signer.issue(&session(OperatorRole::Admin, OperatorAuthMethod::Oidc, -1));
+        assert_eq!(app.call(request(Method::GET, Some(&expired))).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+
+        // Break-glass requests are audited before they are served
+        let break_glass = signer.issue(&session(OperatorRole::Admin, OperatorAuthMethod::BreakGlass, 300));
+        assert_eq!(app.call(request(Method::POST, Some(&break_glass))).await.unwrap().status(), StatusCode::OK);
+
+        // A client cannot present the grant itself; the admin key keeps working
+        let spoofed = Request::get("/admin/feature-flags").header(GRANT_HEADER, "guess").body(Body::empty()).unwrap();
+        assert_eq!(app.call(spoofed).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        let keyed = Request::get("/admin/feature-flags").header("x-admin-key", ADMIN_KEY).body(Body::empty()).unwrap();
+        assert_eq!(app.call(keyed).await.unwrap().status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_disabled_leaves_admin_key_only() {
+        let mut app = router(OperatorAuth::disabled(), SqliteStore::open_in_memory().unwrap());
+        let admin = signer().issue(&session(OperatorRole::Admin, OperatorAuthMethod::Oidc, 300));
+        assert_eq!(app.call(request(Method::GET, Some(&admin))).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        let keyed = Request::get("/admin/feature-flags").header("x-admin-key", ADMIN_KEY).body(Body::empty()).unwrap();
+        assert_eq!(app.call(keyed).await.unwrap().status(), StatusCode::OK);
+    }
+}
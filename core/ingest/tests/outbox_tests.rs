@@ -0,0 +1,189 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/outbox_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for the transactional ingest outbox - messages exist only for committed work, the relay publishes and marks them, failed publishes back off, expired leases are re-claimed, and published rows are purged
+
+/*
+ * Outbox Tests
+ *
+ * Run the relay against the SQLite lab backend with an in-memory publisher: a message enqueued
+ * in a committed unit of work is published exactly once per successful pass, a rolled-back one
+ * never appears, and a claim that is never marked (crash after publish) comes back once its
+ * lease expires.
+ */
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+    use chrono::Utc;
+    use parking_lot::Mutex;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    use ingest::outbox::{self, OutboxConfig, OutboxPublisher, OutboxRelay, TOPIC_TELEMETRY_ACCEPTED};
+    use ingest::storage::{OutboxEntry, OutboxRecord, SqliteStore, TelemetryStore};
+    use ingest::topology::ZoneLabel;
+
+    #[derive(Default)]
+    struct MemoryPublisher {
+        published: Mutex<Vec<Uuid>>,
+        failing: AtomicBool,
+    }
+
+    impl MemoryPublisher {
+        fn published(&self) -> Vec<Uuid> {
+            self.published.lock().clone()
+        }
+    }
+
+    #[async_trait]
+    impl OutboxPublisher for MemoryPublisher {
+        async fn publish(&self, entries: &[OutboxEntry]) -> Vec<Result<(), String>> {
+            if self.failing.load(Ordering::SeqCst) {
+                return vec![Err("consumer unavailable".to_string()); entries.len()];
+            }
+            self.published.lock().extend(entries.iter().map(|e| e.outbox_id));
+            vec![Ok(()); entries.len()]
+        }
+    }
+
+    fn config() -> OutboxConfig {
+        OutboxConfig { publish_addr: Some("127.0.0.1:0".to_string()), ..OutboxConfig::disabled() }
+    }
+
+    fn record() -> OutboxRecord {
+        OutboxRecord { topic: TOPIC_TELEMETRY_ACCEPTED.to_string(), aggregate_id: Uuid::new_v4(), payload: json!({ "event": "test" }) }
+    }
+
+    async fn enqueue(store: &SqliteStore, commit: bool) -> Uuid {
+        let mut tx = store.begin().await.unwrap();
+        let outbox_id = tx.enqueue_outbox(&record()).await.unwrap();
+        if commit {
+            tx.commit().await.unwrap();
+        } else {
+            tx.rollback().await;
+        }
+        outbox_id
+    }
+
+    fn relay(store: &Arc<SqliteStore>, publisher: &Arc<MemoryPublisher>) -> OutboxRelay {
+        OutboxRelay::new(config(), store.clone(), publisher.clone())
+    }
+
+    #[tokio::test]
+    async fn test_committed_message_is_published_and_marked() {
+        let store = Arc::new(SqliteStore::open_in_memory().unwrap());
+        let publisher = Arc::new(MemoryPublisher::default());
+        let outbox_id = enqueue(&store, true).await;
+
+        let pass = relay(&store, &publisher).run_once().await.unwrap();
+        assert_eq!(pass.published, 1);
+        assert_eq!(publisher.published(), vec![outbox_id]);
+
+        // Marked published: a second pass has nothing to send
+        let pass = relay(&store, &publisher).run_once().await.unwrap();
+        assert_eq!(pass.published, 0);
+        assert_eq!(publisher.published().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_rolled_back_work_leaves_no_message() {
+        let store = Arc::new(SqliteStore::open_in_memory().unwrap());
+        enqueue(&store, false).await;
+        {
+            // Dropped without commit
+            let mut tx = store.begin().await.unwrap();
+            tx.enqueue_outbox(&record()).await.unwrap();
+        }
+        assert!(store.claim_outbox(10, Duration::from_secs(60)).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failed_publish_backs_off() {
+        let store = Arc::new(SqliteStore::open_in_memory().unwrap());
+        let publisher = Arc::new(MemoryPublisher::default());
+        publisher.failing.store(true, Ordering::SeqCst);
+        enqueue(&store, true).await;
+
+        let pass = relay(&store, &publisher).run_once().await.unwrap();
+        assert_eq!((pass.published, pass.failed), (0, 1));
+        // Not due again until the backoff elapses
+        assert!(store.claim_outbox(10, Duration::from_secs(60)).await.unwrap().is_empty());
+
+        assert_eq!(outbox::backoff(0), Duration::from_secs(1));
+        assert_eq!(outbox::backoff(3), Duration::from_secs(8));
+        assert_eq!(outbox::backoff(30), Duration::from_secs(300));
+    }
+
+    #[tokio::test]
+    async fn test_retry_counts_attempts() {
+        let store = Arc::new(SqliteStore::open_in_memory().unwrap());
+        let outbox_id = enqueue(&store, true).await;
+
+        let claimed = store.claim_outbox(10, Duration::from_secs(60)).await.unwrap();
+        assert_eq!(claimed[0].attempts, 0);
+        store.retry_outbox(outbox_id, "connect refused", Duration::ZERO).await.unwrap();
+
+        let claimed = store.claim_outbox(10, Duration::from_secs(60)).await.unwrap();
+        assert_eq!(claimed.len(), 1);
+        assert_eq!(claimed[0].outbox_id, outbox_id);
+        assert_eq!(claimed[0].attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_unmarked_claim_is_reclaimed_after_lease() {
+        let store = Arc::new(SqliteStore::open_in_memory().unwrap());
+        let outbox_id = enqueue(&store, true).await;
+
+        // Relay "crashed" after publishing without marking: the expired lease is claimed again
+        assert_eq!(store.claim_outbox(10, Duration::ZERO).await.unwrap()[0].outbox_id, outbox_id);
+        let again = store.claim_outbox(10, Duration::from_secs(60)).await.unwrap();
+        assert_eq!(again.len(), 1);
+        assert_eq!(again[0].outbox_id, outbox_id);
+
+        // While leased it is invisible to another claim
+        assert!(store.claim_outbox(10, Duration::from_secs(60)).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_purge_removes_only_published_rows() {
+        let store = Arc::new(SqliteStore::open_in_memory().unwrap());
+        let published = enqueue(&store, true).await;
+        store.complete_outbox(published).await.unwrap();
+        enqueue(&store, true).await;
+
+        assert_eq!(store.purge_outbox(Utc::now() - chrono::Duration::hours(1)).await.unwrap(), 0);
+        assert_eq!(store.purge_outbox(Utc::now() + chrono::Duration::seconds(1)).await.unwrap(), 1);
+        // The pending message is still there
+        assert_eq!(store.claim_outbox(10, Duration::from_secs(60)).await.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_telemetry_accepted_message_shape() {
+        let raw_event_id = Uuid::new_v4();
+        let agent_id = Uuid::new_v4();
+        let zone = ZoneLabel { site: "hq".to_string(), zone: "hq-office".to_string() };
+        let record =
+            outbox::telemetry_accepted(raw_event_id, "linux_agent", agent_id, "m-1", "process_start", Utc::now(), Some("eu"), Some(&zone));
+        assert_eq!(record.topic, TOPIC_TELEMETRY_ACCEPTED);
+        assert_eq!(record.aggregate_id, raw_event_id);
+        assert_eq!(record.payload["agent_id"], agent_id.to_string());
+        assert_eq!(record.payload["residency_region"], "eu");
+        assert_eq!((record.payload["site"].as_str(), record.payload["zone"].as_str()), (Some("hq"), Some("hq-office")));
+
+        let entry = OutboxEntry {
+            outbox_id: Uuid::new_v4(),
+            topic: record.topic.clone(),
+            aggregate_id: record.aggregate_id,
+            payload: record.payload.clone(),
+            attempts: 0,
+            created_at: Utc::now(),
+        };
+        let message = outbox::message(&entry);
+        assert_eq!(message["outbox_id"], entry.outbox_id.to_string());
+        assert_eq!(message["payload"]["source"], "linux_agent");
+    }
+}
@@ -0,0 +1,97 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/payload_policy_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for the raw_events payload storage policy - full/summary decisions, content-defined sampling, and the summary document
+
+/*
+ * Payload Policy Tests
+ *
+ * In sampled mode, configured categories and event types are always stored in full, routine
+ * events are summarized, and the sample is a pure function of the envelope hash (stable across
+ * re-delivery) that tracks the configured rate.
+ */
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use sha2::{Digest, Sha256};
+
+    use ingest::payload_policy::{summary_payload, PayloadFacts, PayloadPolicyMode, PayloadStoragePolicy};
+    use ingest::protocol::signed_event::SignedEventRef;
+    use ingest::storage::{PayloadStorage, TelemetrySource};
+
+    fn sampled(rate: f64) -> PayloadStoragePolicy {
+        PayloadStoragePolicy { mode: PayloadPolicyMode::Sampled, sample_rate: rate, ..PayloadStoragePolicy::full() }
+    }
+
+    fn facts<'a>(category: Option<&'a str>, event_type: Option<&'a str>) -> PayloadFacts<'a> {
+        PayloadFacts { source: TelemetrySource::LinuxAgent, event_category: category, event_type }
+    }
+
+    fn hash(i: u32) -> Vec<u8> {
+        Sha256::digest(format!("envelope-{}", i).as_bytes()).to_vec()
+    }
+
+    #[test]
+    fn test_full_mode_stores_everything() {
+        let decision = PayloadStoragePolicy::full().decide(facts(Some("process"), Some("Exec")), &hash(0));
+        assert_eq!(decision.storage, PayloadStorage::Full);
+        assert_eq!(decision.reason, "policy:full");
+    }
+
+    #[test]
+    fn test_interesting_events_always_full() {
+        let policy = sampled(0.0);
+        let decision = policy.decide(facts(Some("detection"), None), &hash(1));
+        assert_eq!((decision.storage, decision.reason.as_str()), (PayloadStorage::Full, "category:detection"));
+        let decision = policy.decide(facts(Some("filesystem"), Some("MassWrite")), &hash(2));
+        assert_eq!((decision.storage, decision.reason.as_str()), (PayloadStorage::Full, "event_type:MassWrite"));
+        let decision = policy.decide(facts(Some("process"), Some("Exec")), &hash(3));
+        assert_eq!((decision.storage, decision.reason.as_str()), (PayloadStorage::Summary, "routine"));
+    }
+
+    #[test]
+    fn test_sampling_is_content_defined_and_tracks_rate() {
+        let policy = sampled(0.1);
+        let routine = facts(Some("process"), Some("Exec"));
+        let full = (0..10_000).filter(|i| policy.decide(routine, &hash(*i)).storage == PayloadStorage::Full).count();
+        assert!((800..1200).contains(&full), "{} of 10000 sampled", full);
+
+        // Same envelope, same decision
+        for i in 0..100 {
+            assert_eq!(policy.decide(routine, &hash(i)), policy.decide(routine, &hash(i)));
+        }
+        assert!((0..1000).all(|i| sampled(1.0).decide(routine, &hash(i)).storage == PayloadStorage::Full));
+    }
+
+    #[test]
+    fn test_summary_payload_keeps_hashes_and_fields() {
+        let body = serde_json::to_vec(&json!({
+            "envelope": {
+                "event_id": "6f1c1f4e-8f7e-4b6f-9a55-0d2a3c1b9e01",
+                "timestamp": "2026-10-16T12:00:00+00:00",
+                "component_id": "host-1",
+                "data": { "event_category": "process", "pid": 7 }
+            },
+            "payload_hash": "ab", "signature": "c2ln", "signer_id": "host-1"
+        }))
+        .unwrap();
+        let payload = SignedEventRef::parse(&body).unwrap();
+        let envelope = payload.envelope().unwrap();
+        let envelope_sha256 = Sha256::digest(payload.envelope.get().as_bytes());
+        let data_sha256 = Sha256::digest(envelope.data.get().as_bytes());
+
+        let routine = facts(Some("process"), Some("Exec"));
+        let decision = sampled(0.0).decide(routine, &envelope_sha256);
+        let summary = summary_payload(&decision, routine, &envelope, &envelope_sha256, &data_sha256, json!({ "pid": 7 }))
+            .unwrap();
+        let summary: serde_json::Value = serde_json::from_str(summary.get()).unwrap();
+
+        assert_eq!(summary["payload_storage"], "summary");
+        assert_eq!(summary["reason"], "routine");
+        assert_eq!(summary["event_id"], "6f1c1f4e-8f7e-4b6f-9a55-0d2a3c1b9e01");
+        assert_eq!(summary["envelope_sha256"], hex::encode(envelope_sha256));
+        assert_eq!(summary["data_sha256"], hex::encode(data_sha256));
+        assert_eq!(summary["fields"]["pid"], 7);
+        assert!(summary.get("data").is_none());
+    }
+}
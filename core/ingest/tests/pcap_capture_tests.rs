@@ -0,0 +1,101 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/pcap_capture_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for triggered packet captures - host and incident extraction from detection artifacts, BPF filter construction, pcap upload validation and spooling with a manifest
+
+/*
+ * Packet Capture Tests
+ *
+ * Only a parsed address reaches the BPF filter; uploads must be classic pcaps within the
+ * capture bound; a spooled capture is its pcap plus a manifest the importer can read back.
+ */
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use ingest::pcap_capture::{self, SpoolManifest, PCAP_HEADER_LEN};
+    use std::net::IpAddr;
+    use tempfile::TempDir;
+    use uuid::Uuid;
+
+    fn pcap(records: usize) -> Vec<u8> {
+        let mut data = vec![0xd4, 0xc3, 0xb2, 0xa1, 2, 0, 4, 0];
+        data.resize(PCAP_HEADER_LEN, 0);
+        data.extend(std::iter::repeat_n(0u8, records * 80));
+        data
+    }
+
+    #[test]
+    fn test_host_and_filter_from_artifacts() {
+        let artifacts = serde_json::json!({
+            "host_ip": " 10.1.2.3 ",
+            "incident_id": "7C2F4A56-0A3E-4C1B-9A55-1F1F4C0E2B11",
+        });
+        let host = pcap_capture::host_address(&artifacts).unwrap();
+        assert_eq!(host, "10.1.2.3".parse::<IpAddr>().unwrap());
+        assert_eq!(pcap_capture::bpf_filter(host), "host 10.1.2.3");
+        assert_eq!(
+            pcap_capture::incident_id(&artifacts).unwrap().to_string(),
+            "7c2f4a56-0a3e-4c1b-9a55-1f1f4c0e2b11"
+        );
+
+        let v6 = pcap_capture::host_address(&serde_json::json!({"host_ip": "fd00::5"})).unwrap();
+        assert_eq!(pcap_capture::bpf_filter(v6), "host fd00::5");
+
+        // Filter syntax in an artifact is not an address
+        for bad in ["10.1.2.3 or port 22", "host 10.1.2.3", "", "evil.example"] {
+            assert!(pcap_capture::host_address(&serde_json::json!({"host_ip": bad})).is_none(), "accepted {:?}", bad);
+        }
+        assert!(pcap_capture::host_address(&serde_json::json!({"host_ip": 167837955})).is_none());
+        assert!(pcap_capture::incident_id(&serde_json::json!({"incident_id": "INC-42"})).is_none());
+    }
+
+    #[test]
+    fn test_uploads_must_be_bounded_pcaps() {
+        let data = pcap(4);
+        assert!(pcap_capture::check_pcap(&data, data.len() as u64).is_ok());
+        assert!(pcap_capture::check_pcap(&data, data.len() as u64 - 1).is_err(), "over the capture bound");
+        assert!(pcap_capture::check_pcap(&data[..PCAP_HEADER_LEN - 1], 1 << 20).is_err(), "truncated header");
+
+        let mut nanos = data.clone();
+        nanos[..4].copy_from_slice(&[0x4d, 0x3c, 0xb2, 0xa1]);
+        assert!(pcap_capture::check_pcap(&nanos, 1 << 20).is_ok());
+
+        // pcapng and arbitrary files are refused
+        let mut pcapng = data.clone();
+        pcapng[..4].copy_from_slice(&[0x0a, 0x0d, 0x0d, 0x0a]);
+        assert!(pcap_capture::check_pcap(&pcapng, 1 << 20).is_err());
+        assert!(pcap_capture::check_pcap(b"GET / HTTP/1.1\r\nHost: x\r\n\r\n", 1 << 20).is_err());
+    }
+
+    #[test]
+    fn test_spool_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let capture_id = Uuid::new_v4();
+        let data = pcap(2);
+        let now = Utc::now();
+        let manifest = SpoolManifest {
+            capture_id,
+            detection_id: Some(Uuid::new_v4()),
+            incident_id: None,
+            host_ip: "10.1.2.3".to_string(),
+            bpf_filter: "host 10.1.2.3".to_string(),
+            probe_agent_id: Uuid::new_v4(),
+            probe_component_id: "dpi-probe-01".to_string(),
+            requested_at: now,
+            claimed_at: now,
+            uploaded_at: now,
+            pcap_file: format!("{}.pcap", capture_id),
+            pcap_sha256: "00".repeat(32),
+            pcap_bytes: data.len() as u64,
+        };
+        pcap_capture::write_spool(dir.path(), &manifest, &data).unwrap();
+        assert_eq!(std::fs::read(dir.path().join(&manifest.pcap_file)).unwrap(), data);
+        let json = std::fs::read(dir.path().join(format!("{}.json", capture_id))).unwrap();
+        assert_eq!(serde_json::from_slice::<SpoolManifest>(&json).unwrap(), manifest);
+        // No temporary files are left behind
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+
+        pcap_capture::remove_spool(dir.path(), capture_id);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+}
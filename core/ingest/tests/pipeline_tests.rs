@@ -0,0 +1,295 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/pipeline_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for the sharded ingest pipeline - stable agent-to-shard routing, per-agent order, backlog and realtime lanes, bounded queues, latency averages and isolation of a failing unit of work
+
+/*
+ * Sharded Ingest Pipeline Tests
+ *
+ * An agent always maps to the same shard; its units of work complete in submission order even
+ * when later ones are faster; replayed backlog interleaves with fresh events at the configured
+ * ratio, and an agent's events stay in one lane; a full queue refuses instead of growing and
+ * shows in the agent's load; units of work feed the latency averages; a unit of work that panics
+ * fails only its own caller.
+ *
+ * Shards on their own Postgres connections extend the audit chain in parallel. That test needs a
+ * disposable database with the authoritative schema applied and skips without
+ * RANSOMEYE_TEST_PIPELINE_DB=1:
+ *
+ *   RANSOMEYE_TEST_PIPELINE_DB=1 DB_HOST=... DB_PORT=... DB_NAME=... DB_USER=... DB_PASS=... \
+ *       cargo test -p ingest --test pipeline_tests
+ */
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use chrono::{DateTime, Utc};
+    use parking_lot::Mutex;
+    use uuid::Uuid;
+
+    use ingest::pipeline::{shard_for, Lane, PipelineConfig, PipelineError, ShardedPipeline};
+    use ingest::storage::postgres::{self, PostgresStore};
+    use ingest::storage::sqlite::SqliteStore;
+    use ingest::storage::{AuditRecord, StorageError, TelemetryStore};
+    use sha2::{Digest, Sha256};
+
+    fn config(shards: usize, queue_depth: usize) -> PipelineConfig {
+        PipelineConfig { shards, queue_depth, backlog_age: Duration::from_secs(300), realtime_ratio: 2 }
+    }
+
+    fn start(pipeline: &ShardedPipeline, shards: usize) {
+        let store: Arc<dyn TelemetryStore> = Arc::new(SqliteStore::open_in_memory().unwrap());
+        pipeline.start(vec![store; shards]).unwrap();
+    }
+
+    fn started(shards: usize, queue_depth: usize) -> Arc<ShardedPipeline> {
+        let pipeline = Arc::new(ShardedPipeline::new(config(shards, queue_depth)));
+        start(&pipeline, shards);
+        pipeline
+    }
+
+    /// Queue an event of `agent` observed at `observed_at` that records `label` when it runs.
+    fn queue_event(
+        pipeline: &Arc<ShardedPipeline>,
+        order: &Arc<Mutex<Vec<&'static str>>>,
+        agent: Uuid,
+        observed_at: DateTime<Utc>,
+        label: &'static str,
+    ) -> tokio::task::JoinHandle<()> {
+        let (pipeline, order) = (pipeline.clone(), order.clone());
+        tokio::spawn(async move {
+            pipeline.run_event(agent, observed_at, move |_store| async move { order.lock().push(label) }).await.unwrap()
+        })
+    }
+
+    #[test]
+    fn test_agent_maps_to_one_shard() {
+        let agents: Vec<Uuid> = (0..256).map(|_| Uuid::new_v4()).collect();
+        let mut used = [false; 8];
+        for agent in &agents {
+            let shard = shard_for(*agent, 8);
+            assert!(shard < 8);
+            assert_eq!(shard, shard_for(*agent, 8));
+            used[shard] = true;
+        }
+        assert!(used.iter().all(|u| *u), "256 random agents should reach every one of 8 shards");
+        assert_eq!(shard_for(agents[0], 1), 0);
+    }
+
+    #[tokio::test]
+    async fn test_agent_events_complete_in_submission_order() {
+        let pipeline = started(4, 64);
+        let agent = Uuid::new_v4();
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        // Current-thread runtime: spawned tasks enqueue in spawn order; later events finish faster
+        let handles: Vec<_> = (0..20u64)
+            .map(|i| {
+                let (pipeline, order) = (pipeline.clone(), order.clone());
+                tokio::spawn(async move {
+                    pipeline
+                        .run(agent, move |_store| async move {
+                            tokio::time::sleep(Duration::from_millis(20 - i)).await;
+                            order.lock().push(i);
+                        })
+                        .await
+                })
+            })
+            .collect();
+        for h in handles {
+            h.await.unwrap().unwrap();
+        }
+        assert_eq!(*order.lock(), (0..20).collect::<Vec<_>>());
+        let stats = pipeline.stats();
+        assert_eq!(stats.per_shard.iter().map(|s| s.processed).sum::<u64>(), 20);
+        assert_eq!(stats.per_shard[shard_for(agent, 4)].processed, 20);
+        // Units of work slept 1-20ms; idle shards have no average yet
+        assert!(stats.latency_us >= 1_000, "latency_us={}", stats.latency_us);
+        assert!(stats.per_shard[shard_for(agent, 4)].latency_us >= 1_000);
+        assert_eq!(stats.per_shard.iter().filter(|s| s.latency_us == 0).count(), 3);
+        let load = pipeline.load(agent);
+        assert_eq!(load.shard_latency, Duration::from_micros(stats.per_shard[shard_for(agent, 4)].latency_us));
+        assert_eq!((load.shard_queued, load.queued, load.shards), (0, 0, 4));
+    }
+
+    #[test]
+    fn test_lane_follows_event_age() {
+        let pipeline = ShardedPipeline::new(config(1, 8));
+        let now = Utc::now();
+        assert_eq!(pipeline.lane_for(now, now), Lane::Realtime);
+        assert_eq!(pipeline.lane_for(now - chrono::Duration::seconds(300), now), Lane::Realtime);
+        assert_eq!(pipeline.lane_for(now - chrono::Duration::seconds(301), now), Lane::Backlog);
+        // Clock skew: events from the future are current
+        assert_eq!(pipeline.lane_for(now + chrono::Duration::hours(1), now), Lane::Realtime);
+    }
+
+    #[tokio::test]
+    async fn test_backlog_interleaves_with_realtime() {
+        // Not started: everything is queued before the shard runs anything
+        let pipeline = Arc::new(ShardedPipeline::new(config(1, 64)));
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let (replaying, now) = (Uuid::new_v4(), Utc::now());
+        let mut handles = Vec::new();
+        for _ in 0..4 {
+            handles.push(queue_event(&pipeline, &order, replaying, now - chrono::Duration::hours(3), "old"));
+        }
+        for _ in 0..4 {
+            handles.push(queue_event(&pipeline, &order, Uuid::new_v4(), now, "new"));
+        }
+        tokio::task::yield_now().await;
+        let stats = pipeline.stats();
+        assert_eq!((stats.per_shard[0].queued, stats.per_shard[0].backlog_queued), (8, 4));
+
+        start(&pipeline, 1);
+        for h in handles {
+            h.await.unwrap();
+        }
+        // Two realtime units per backlog unit while both lanes hold work, then the rest
+        assert_eq!(*order.lock(), ["new", "new", "old", "new", "new", "old", "old", "old"]);
+        let stats = pipeline.stats();
+        assert_eq!((stats.per_shard[0].processed, stats.per_shard[0].backlog_processed), (8, 4));
+    }
+
+    #[tokio::test]
+    async fn test_agent_events_stay_in_one_lane() {
+        let pipeline = Arc::new(ShardedPipeline::new(config(1, 64)));
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let (replaying, now) = (Uuid::new_v4(), Utc::now());
+        // The replaying agent's fresh event waits behind its own backlog, not in the realtime lane
+        let handles = vec![
+            queue_event(&pipeline, &order, replaying, now - chrono::Duration::hours(3), "replaying old"),
+            queue_event(&pipeline, &order, replaying, now, "replaying new"),
+            queue_event(&pipeline, &order, Uuid::new_v4(), now, "other new"),
+        ];
+        tokio::task::yield_now().await;
+        assert_eq!(pipeline.stats().per_shard[0].backlog_queued, 2);
+
+        start(&pipeline, 1);
+        for h in handles {
+            h.await.unwrap();
+        }
+        assert_eq!(*order.lock(), ["other new", "replaying old", "replaying new"]);
+
+        // Nothing queued any more: the agent's next fresh event is realtime again
+        queue_event(&pipeline, &order, replaying, Utc::now(), "replaying current").await.unwrap();
+        assert_eq!(pipeline.stats().per_shard[0].backlog_processed, 2);
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_refuses() {
+        // Not started: the first unit of work stays queued and fills the shard
+        let pipeline = Arc::new(ShardedPipeline::new(config(1, 1)));
+        let agent = Uuid::new_v4();
+        let first = tokio::spawn({
+            let pipeline = pipeline.clone();
+            async move { pipeline.run(agent, |_store| async {}).await }
+        });
+        tokio::task::yield_now().await;
+
+        assert_eq!(pipeline.run(agent, |_store| async {}).await, Err(PipelineError::Busy));
+        let stats = pipeline.stats();
+        assert!(!stats.started);
+        assert_eq!((stats.per_shard[0].queued, stats.per_shard[0].refused), (1, 1));
+        let load = pipeline.load(agent);
+        assert_eq!((load.shard_fill_pct(), load.fill_pct()), (100, 100));
+        first.abort();
+    }
+
+    #[tokio::test]
+    async fn test_panicking_work_fails_only_its_caller() {
+        let pipeline = started(1, 8);
+        let agent = Uuid::new_v4();
+        let failed = pipeline.run(agent, |_store| async { panic!("unit of work failed") }).await;
+        assert_eq!(failed, Err::<(), _>(PipelineError::Unavailable));
+        assert_eq!(pipeline.run(agent, |_store| async { 7 }).await, Ok(7));
+        assert!(pipeline.start(Vec::new()).is_err());
+    }
+
+    /// One unit of work as the event handlers run it: two audit rows in one transaction.
+    async fn append_audit_pair(store: Arc<dyn TelemetryStore>, run_id: String, agent: Uuid, unit: usize) -> Result<(), StorageError> {
+        let mut tx = store.begin().await?;
+        for step in 0..2 {
+            let payload = serde_json::json!({ "run_id": run_id, "agent": agent.to_string(), "unit": unit, "step": step });
+            let audit = AuditRecord {
+                actor_component_id: None,
+                actor_agent_id: None,
+                action: "pipeline_audit_chain_test".to_string(),
+                object_type: "other".to_string(),
+                object_id: None,
+                event_time: None,
+                payload_sha256: Sha256::digest(payload.to_string().as_bytes()).to_vec(),
+                payload_json: payload,
+            };
+            if let Err(e) = tx.append_audit(&audit).await {
+                tx.rollback().await;
+                return Err(e);
+            }
+            tokio::task::yield_now().await;
+        }
+        tx.commit().await
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_parallel_shards_extend_one_audit_chain() {
+        if std::env::var("RANSOMEYE_TEST_PIPELINE_DB").as_deref() != Ok("1") {
+            eprintln!("skipping: RANSOMEYE_TEST_PIPELINE_DB != 1");
+            return;
+        }
+        const SHARDS: usize = 2;
+        const AGENTS_PER_SHARD: usize = 4;
+        const UNITS_PER_AGENT: usize = 25;
+
+        let pipeline = Arc::new(ShardedPipeline::new(config(SHARDS, 1024)));
+        let mut stores: Vec<Arc<dyn TelemetryStore>> = Vec::new();
+        for _ in 0..SHARDS {
+            stores.push(Arc::new(PostgresStore::new(postgres::connect_from_env().await.expect("connect"))));
+        }
+        pipeline.start(stores).unwrap();
+
+        let mut agents: Vec<Uuid> = Vec::new();
+        for shard in 0..SHARDS {
+            agents.extend(
+                std::iter::repeat_with(Uuid::new_v4).filter(|a| shard_for(*a, SHARDS) == shard).take(AGENTS_PER_SHARD),
+            );
+        }
+        let run_id = Uuid::new_v4().to_string();
+        let mut handles = Vec::new();
+        for agent in agents {
+            for unit in 0..UNITS_PER_AGENT {
+                let (pipeline, run_id) = (pipeline.clone(), run_id.clone());
+                handles.push(tokio::spawn(async move {
+                    pipeline.run(agent, move |store| append_audit_pair(store, run_id, agent, unit)).await
+                }));
+            }
+        }
+        // A lock-order cycle between shards would hang here rather than fail
+        tokio::time::timeout(Duration::from_secs(60), async {
+            for h in handles {
+                h.await.unwrap().unwrap().expect("unit of work");
+            }
+        })
+        .await
+        .expect("shards deadlocked extending the audit chain");
+
+        let db = postgres::connect_from_env().await.expect("connect");
+        let rows = db
+            .query(
+                r#"
+                SELECT audit_id, prev_audit_id
+                FROM immutable_audit_log
+                WHERE action = 'pipeline_audit_chain_test' AND payload_json->>'run_id' = $1
+                ORDER BY created_at ASC, audit_id ASC
+                "#,
+                &[&run_id],
+            )
+            .await
+            .expect("query chain");
+        assert_eq!(rows.len(), SHARDS * AGENTS_PER_SHARD * UNITS_PER_AGENT * 2);
+        for w in rows.windows(2) {
+            assert_eq!(w[1].get::<_, Option<Uuid>>(1), Some(w[0].get::<_, Uuid>(0)), "audit chain forked");
+        }
+        let stats = pipeline.stats();
+        assert!(stats.per_shard.iter().all(|s| s.processed == (AGENTS_PER_SHARD * UNITS_PER_AGENT) as u64));
+    }
+}
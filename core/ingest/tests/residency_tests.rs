@@ -0,0 +1,108 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/residency_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for data residency routing - region validation, enrollment tagging defaults, cross-region refusal, explicit allowed routes, untagged agents and the raw_events classification
+
+/*
+ * Residency Tests
+ *
+ * An instance accepts its own region and only the routes the policy lists (directed); untagged
+ * agents follow RANSOMEYE_RESIDENCY_UNTAGGED; the accepted event's classification is the agent's
+ * region and survives the raw_events round trip.
+ */
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use serde_json::json;
+    use sha2::{Digest, Sha256};
+
+    use ingest::residency::{validate_region, ResidencyDecision, ResidencyPolicy, ResidencyRoute, UntaggedAgents};
+    use ingest::storage::{PayloadStorage, RawEventRecord, SqliteStore, TelemetryQuery, TelemetrySource, TelemetryStore};
+
+    fn eu_west(routes: &[(&str, &str)], untagged: UntaggedAgents) -> ResidencyPolicy {
+        ResidencyPolicy {
+            local_region: Some("eu-west".to_string()),
+            allowed_routes: routes
+                .iter()
+                .map(|(from, to)| ResidencyRoute { from: from.to_string(), to: to.to_string() })
+                .collect(),
+            untagged,
+        }
+    }
+
+    fn accepted(region: &str, cross_region: bool) -> ResidencyDecision {
+        ResidencyDecision::Accepted { region: Some(region.to_string()), cross_region }
+    }
+
+    #[test]
+    fn test_region_validation() {
+        assert!(validate_region("eu-west-1").is_ok());
+        assert!(validate_region("EU-WEST").is_err());
+        assert!(validate_region("1eu").is_err());
+        assert!(validate_region("").is_err());
+        assert!(validate_region(&"a".repeat(33)).is_err());
+    }
+
+    #[test]
+    fn test_enrollment_defaults_to_instance_region() {
+        let policy = eu_west(&[], UntaggedAgents::Local);
+        assert_eq!(policy.enrollment_region(None).unwrap().as_deref(), Some("eu-west"));
+        assert_eq!(policy.enrollment_region(Some("us-east")).unwrap().as_deref(), Some("us-east"));
+        assert!(policy.enrollment_region(Some("US East")).is_err());
+        assert_eq!(ResidencyPolicy::disabled().enrollment_region(None).unwrap(), None);
+    }
+
+    #[test]
+    fn test_cross_region_refused_unless_route_allowed() {
+        let strict = eu_west(&[], UntaggedAgents::Local);
+        assert_eq!(strict.decide(Some("eu-west")), accepted("eu-west", false));
+        assert!(matches!(strict.decide(Some("us-east")), ResidencyDecision::Refused { .. }));
+
+        // Routes are directed: eu-central may deliver here, eu-west may not deliver to eu-central
+        let routed = eu_west(&[("eu-central", "eu-west"), ("eu-west", "us-east")], UntaggedAgents::Local);
+        assert_eq!(routed.decide(Some("eu-central")), accepted("eu-central", true));
+        assert!(matches!(routed.decide(Some("us-east")), ResidencyDecision::Refused { .. }));
+    }
+
+    #[test]
+    fn test_untagged_agents() {
+        assert_eq!(eu_west(&[], UntaggedAgents::Local).decide(None), accepted("eu-west", false));
+        assert!(matches!(
+            eu_west(&[], UntaggedAgents::Reject).decide(None),
+            ResidencyDecision::Refused { agent_region: None, .. }
+        ));
+    }
+
+    #[test]
+    fn test_disabled_policy_accepts_everything_and_keeps_agent_region() {
+        let policy = ResidencyPolicy::disabled();
+        assert_eq!(policy.decide(Some("us-east")), accepted("us-east", false));
+        assert_eq!(policy.decide(None), ResidencyDecision::Accepted { region: None, cross_region: false });
+    }
+
+    #[tokio::test]
+    async fn test_classification_round_trips_through_raw_events() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let agent_id = store.resolve_agent("lab-host-1", TelemetrySource::LinuxAgent).await.unwrap();
+        let payload = json!({ "event": "test" });
+        let record = RawEventRecord {
+            source: TelemetrySource::LinuxAgent,
+            agent_id,
+            observed_at: Utc::now(),
+            event_name: "process_start".to_string(),
+            payload_sha256: Sha256::digest(payload.to_string().as_bytes()).to_vec(),
+            payload_json: serde_json::value::to_raw_value(&payload).unwrap(),
+            payload_storage: PayloadStorage::Full,
+            payload_storage_reason: "policy:full".to_string(),
+            residency_region: Some("eu-west".to_string()),
+            zone: None,
+        };
+        let mut tx = store.begin().await.unwrap();
+        tx.insert_raw(&record).await.unwrap();
+        tx.commit().await.unwrap();
+
+        let events = store.query(&TelemetryQuery { limit: 10, ..Default::default() }).await.unwrap();
+        assert_eq!(events[0].residency_region.as_deref(), Some("eu-west"));
+        assert_eq!(serde_json::to_value(&events[0]).unwrap()["residency_region"], "eu-west");
+    }
+}
@@ -0,0 +1,117 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/retention_runs_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for the retention schedule assessment - failed, overdue and never-run alerts from live runs, dry runs ignored, failure streaks, max age bounds and failed run records
+
+/*
+ * Retention Runs Tests
+ *
+ * Only live runs count: a recent successful dry run does not keep the schedule healthy. The
+ * latest live run failing raises `failed`; no successful live run within max_age_hours raises
+ * `overdue`; none at all raises `never_run`.
+ */
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+    use ingest::retention_runs::{self, RetentionAlertKind, RetentionRun, RunMode, RunStatus};
+    use uuid::Uuid;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 6, 1, 12, 0, 0).unwrap()
+    }
+
+    fn run(mode: RunMode, hours_ago: i64, ok: bool) -> RetentionRun {
+        let started_at = now() - Duration::hours(hours_ago);
+        let ended_at = started_at + Duration::minutes(2);
+        if !ok {
+            return RetentionRun::failed(Uuid::new_v4(), mode, started_at, ended_at, "FAIL-CLOSED: lock timeout");
+        }
+        RetentionRun {
+            run_id: Uuid::new_v4(),
+            mode,
+            status: RunStatus::Succeeded,
+            started_at,
+            ended_at,
+            duration_ms: 120_000,
+            tables: 12,
+            rows_would_purge: 500,
+            rows_deleted: if mode == RunMode::Live { 480 } else { 0 },
+            held_rows_skipped: 20,
+            referenced_rows_skipped: 0,
+            orphan_rows_deleted: 0,
+            orphan_rows_marked: 0,
+            lock_timeouts: 0,
+            audit_id: Some(Uuid::new_v4()),
+            error: None,
+            table_stats: serde_json::json!([]),
+        }
+    }
+
+    fn kinds(status: &retention_runs::RetentionStatus) -> Vec<RetentionAlertKind> {
+        status.alerts.iter().map(|a| a.kind).collect()
+    }
+
+    #[test]
+    fn test_recent_success_is_healthy() {
+        let ok = run(RunMode::Live, 1, true);
+        let status = retention_runs::assess(&[ok.clone()], Some(ok.clone()), 3, now());
+        assert!(status.healthy);
+        assert_eq!(status.consecutive_failures, 0);
+        assert_eq!(status.last_run.as_ref().map(|r| r.run_id), Some(ok.run_id));
+    }
+
+    #[test]
+    fn test_failed_runs_counted_and_alerted() {
+        let ok = run(RunMode::Live, 3, true);
+        let failed = [run(RunMode::Live, 1, false), run(RunMode::Live, 2, false)];
+        let recent = [failed[0].clone(), failed[1].clone(), ok.clone()];
+        let status = retention_runs::assess(&recent, Some(ok), 3, now());
+        assert!(!status.healthy);
+        assert_eq!(status.consecutive_failures, 2);
+        assert_eq!(kinds(&status), vec![RetentionAlertKind::Failed]);
+        assert_eq!(status.alerts[0].run_id, Some(failed[0].run_id));
+        assert!(status.alerts[0].message.contains("lock timeout"));
+    }
+
+    #[test]
+    fn test_overdue_ignores_dry_runs() {
+        let ok = run(RunMode::Live, 5, true);
+        let recent = [run(RunMode::DryRun, 1, true), ok.clone()];
+        let status = retention_runs::assess(&recent, Some(ok.clone()), 3, now());
+        assert_eq!(kinds(&status), vec![RetentionAlertKind::Overdue]);
+        assert_eq!(status.alerts[0].run_id, Some(ok.run_id));
+        assert_eq!(status.last_run.map(|r| r.run_id), Some(ok.run_id));
+        // A wider limit covers the gap
+        assert!(retention_runs::assess(&recent, Some(ok), 6, now()).healthy);
+    }
+
+    #[test]
+    fn test_never_run() {
+        let status = retention_runs::assess(&[], None, 3, now());
+        assert_eq!(kinds(&status), vec![RetentionAlertKind::NeverRun]);
+        assert!(status.last_run.is_none());
+
+        let failed = run(RunMode::Live, 1, false);
+        let status = retention_runs::assess(&[failed], None, 3, now());
+        assert_eq!(kinds(&status), vec![RetentionAlertKind::Failed, RetentionAlertKind::NeverRun]);
+        assert_eq!(status.consecutive_failures, 1);
+    }
+
+    #[test]
+    fn test_failed_record_truncates_error() {
+        let long = "é".repeat(5000);
+        let r = RetentionRun::failed(Uuid::new_v4(), RunMode::Live, now(), now(), &long);
+        assert_eq!(r.status, RunStatus::Failed);
+        assert!(r.error.as_ref().unwrap().len() <= 4096);
+        assert!(r.audit_id.is_none());
+        assert_eq!(RunMode::parse(r.mode.as_str()), Some(RunMode::Live));
+        assert_eq!(RunStatus::parse("failed"), Some(RunStatus::Failed));
+    }
+
+    #[test]
+    fn test_max_age_bounds() {
+        assert!(retention_runs::check_max_age_hours(0).is_err());
+        assert_eq!(retention_runs::check_max_age_hours(3), Ok(3));
+        assert!(retention_runs::check_max_age_hours(retention_runs::MAX_MAX_AGE_HOURS + 1).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/rule_metrics_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for detection rule metrics - derived suppression and false positive rates, noisy and dead rule flags of the tuning report, threshold and incident close validation
+
+/*
+ * Rule Metrics Tests
+ *
+ * A rule is noisy once it has enough firings and a high false positive or suppression rate; it
+ * is dead once it has not fired for dead_after_days. A dead rule is not also reported as noisy.
+ */
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+    use ingest::rule_metrics::{self, Disposition, RuleMetrics, TuningThresholds};
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 6, 1, 0, 0, 0).unwrap()
+    }
+
+    fn rule(name: &str, firings: i64, suppressed: i64, tp: i64, fp: i64, last_fired_days_ago: i64) -> RuleMetrics {
+        RuleMetrics {
+            detection_engine: "correlation".to_string(),
+            detection_name: name.to_string(),
+            first_fired_at: now() - Duration::days(90),
+            last_fired_at: now() - Duration::days(last_fired_days_ago),
+            window_start: now() - Duration::days(30),
+            firings,
+            suppressed,
+            true_positives: tp,
+            false_positives: fp,
+            triaged: tp + fp,
+            median_triage_secs: (tp + fp > 0).then_some(600.0),
+            refreshed_at: now(),
+        }
+    }
+
+    #[test]
+    fn test_derived_rates() {
+        let r = rule("r", 40, 10, 3, 1, 0);
+        assert_eq!(r.suppression_rate(), 0.25);
+        assert_eq!(r.false_positive_rate(), Some(0.25));
+
+        let silent = rule("silent", 0, 0, 0, 0, 45);
+        assert_eq!(silent.suppression_rate(), 0.0);
+        assert_eq!(silent.false_positive_rate(), None);
+    }
+
+    #[test]
+    fn test_tuning_report_flags_noisy_and_dead_rules() {
+        let rules = vec![
+            rule("false_positive_heavy", 50, 0, 2, 8, 0),
+            rule("mostly_suppressed", 100, 80, 0, 0, 1),
+            rule("healthy", 100, 5, 9, 1, 0),
+            rule("few_firings", 5, 5, 0, 5, 0),
+            rule("silent", 0, 0, 0, 0, 45),
+            rule("older_silent", 0, 0, 0, 0, 60),
+        ];
+        let report = rule_metrics::tuning_report(rules, TuningThresholds::default(), now());
+        assert_eq!(report.rules, 6);
+
+        let noisy: Vec<&str> = report.noisy.iter().map(|e| e.metrics.detection_name.as_str()).collect();
+        assert_eq!(noisy, ["false_positive_heavy", "mostly_suppressed"]);
+        assert_eq!(report.noisy[0].false_positive_rate, Some(0.8));
+        assert!(report.noisy[0].reasons[0].starts_with("false positive rate 0.80"));
+        assert!(report.noisy[1].reasons[0].starts_with("suppression rate 0.80"));
+
+        let dead: Vec<&str> = report.dead.iter().map(|e| e.metrics.detection_name.as_str()).collect();
+        assert_eq!(dead, ["older_silent", "silent"]);
+    }
+
+    #[test]
+    fn test_thresholds_change_the_report() {
+        let rules = vec![rule("borderline", 10, 3, 6, 4, 10)];
+        let report = rule_metrics::tuning_report(rules.clone(), TuningThresholds::default(), now());
+        assert!(report.noisy.is_empty() && report.dead.is_empty());
+
+        let strict = TuningThresholds {
+            noisy_min_firings: 10,
+            noisy_false_positive_rate: 0.4,
+            noisy_suppression_rate: 0.9,
+            dead_after_days: 7,
+        };
+        let report = rule_metrics::tuning_report(rules, strict, now());
+        assert!(report.noisy.is_empty());
+        assert_eq!(report.dead.len(), 1);
+
+        let strict = TuningThresholds { dead_after_days: 30, ..strict };
+        let report = rule_metrics::tuning_report(vec![rule("borderline", 10, 3, 6, 4, 10)], strict, now());
+        assert_eq!(report.noisy.len(), 1);
+    }
+
+    #[test]
+    fn test_threshold_validation() {
+        assert!(TuningThresholds::default().validate().is_ok());
+        assert!(TuningThresholds { noisy_min_firings: 0, ..Default::default() }.validate().is_err());
+        assert!(TuningThresholds { noisy_false_positive_rate: 0.0, ..Default::default() }.validate().is_err());
+        assert!(TuningThresholds { noisy_suppression_rate: 1.5, ..Default::default() }.validate().is_err());
+        assert!(TuningThresholds { noisy_suppression_rate: f64::NAN, ..Default::default() }.validate().is_err());
+        assert!(TuningThresholds { dead_after_days: 0, ..Default::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn test_incident_close_validation() {
+        assert!(rule_metrics::check_closure("alice", "benign admin script").is_ok());
+        assert!(rule_metrics::check_closure(" ", "benign").is_err());
+        assert!(rule_metrics::check_closure("alice", "").is_err());
+        assert!(rule_metrics::check_closure("alice", &"x".repeat(rule_metrics::MAX_REASON_BYTES + 1)).is_err());
+
+        assert_eq!(Disposition::parse("false_positive"), Some(Disposition::FalsePositive));
+        assert_eq!(Disposition::TruePositive.as_str(), "true_positive");
+        assert_eq!(Disposition::parse("benign"), None);
+        assert_eq!(serde_json::to_string(&Disposition::FalsePositive).unwrap(), "\"false_positive\"");
+    }
+}
@@ -0,0 +1,80 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_ingestion/tests/runtime_controls_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for runtime log filter / sampling overrides - validation, TTL generation guard, and sampling ratio
+
+/*
+ * Runtime Controls Tests
+ * 
+ * Tests that verify invalid overrides are rejected, stale revert timers never undo a
+ * newer override, and diagnostic sampling honours the configured ratio exactly.
+ */
+
+#[cfg(test)]
+mod tests {
+    use tracing_subscriber::filter::Targets;
+    use tracing_subscriber::reload;
+    use tracing_subscriber::Registry;
+    use ingest::runtime_controls::{RuntimeChange, RuntimeControlConfig, RuntimeControls};
+
+    fn cfg(ratio: f64) -> RuntimeControlConfig {
+        RuntimeControlConfig {
+            default_ttl_secs: 60,
+            max_ttl_secs: 600,
+            signal_log_filter: "debug".to_string(),
+            baseline_sample_ratio: ratio,
+        }
+    }
+
+    fn controls(ratio: f64) -> (reload::Layer<Targets, Registry>, RuntimeControls) {
+        let (layer, handle) = reload::Layer::new("info".parse::<Targets>().unwrap());
+        let controls = RuntimeControls::new(Some(handle), "info", cfg(ratio)).unwrap();
+        (layer, controls)
+    }
+
+    #[test]
+    fn test_invalid_override_rejected() {
+        let (_layer, c) = controls(1.0);
+        assert!(c.apply(&RuntimeChange::default()).is_err());
+        assert!(c.apply(&RuntimeChange { sample_ratio: Some(1.5), ..Default::default() }).is_err());
+        assert!(c.apply(&RuntimeChange { log_filter: Some("ingest=loud".to_string()), ..Default::default() }).is_err());
+        assert!(c.apply(&RuntimeChange { sample_ratio: Some(0.5), ttl_secs: Some(601), ..Default::default() }).is_err());
+        assert_eq!(c.state().generation, 0);
+    }
+
+    #[test]
+    fn test_apply_and_revert() {
+        let (_layer, c) = controls(1.0);
+        let applied = c.apply(&RuntimeChange {
+            log_filter: Some("debug".to_string()),
+            sample_ratio: Some(0.25),
+            ttl_secs: None,
+        }).unwrap();
+        assert_eq!(applied.log_filter, "debug");
+        assert_eq!(applied.ttl_secs, Some(60));
+
+        let restored = c.revert_if(applied.generation).unwrap().unwrap();
+        assert_eq!(restored.log_filter, "info");
+        assert_eq!(restored.sample_ratio, 1.0);
+        assert_eq!(restored.ttl_secs, None);
+    }
+
+    #[test]
+    fn test_stale_revert_ignored() {
+        let (_layer, c) = controls(1.0);
+        let first = c.apply(&RuntimeChange { sample_ratio: Some(0.5), ..Default::default() }).unwrap();
+        let second = c.apply(&RuntimeChange { sample_ratio: Some(0.1), ..Default::default() }).unwrap();
+        assert_eq!(c.revert_if(first.generation).unwrap(), None);
+        assert_eq!(c.state().sample_ratio, 0.1);
+        assert!(c.revert_if(second.generation).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_sampling_ratio_exact() {
+        let (_layer, c) = controls(0.25);
+        let sampled = (0..1000).filter(|_| c.should_sample_event()).count();
+        assert_eq!(sampled, 250);
+
+        let (_layer, none) = controls(0.0);
+        assert!(!(0..100).any(|_| none.should_sample_event()));
+    }
+}
@@ -0,0 +1,170 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/sandbox_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for sandbox detonation submissions - verdict thresholds, Cuckoo/CAPE response parsing, sample and report spooling with a manifest, and the malicious-verdict detection
+
+/*
+ * Sandbox Tests
+ *
+ * Scores map onto verdicts by the configured thresholds; task ids, states and scores are read
+ * from both Cuckoo and CAPE responses; a spooled report is its bytes plus a manifest the
+ * importer can read back; a malicious verdict raises one detection per file.
+ */
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use ingest::sandbox::{self, SandboxConfig, SandboxSubmission, SpoolManifest, SubmitMode, TaskState, Verdict};
+    use std::path::PathBuf;
+    use std::time::Duration;
+    use tempfile::TempDir;
+    use uuid::Uuid;
+
+    const SHA256: &str = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+
+    fn config(spool_dir: PathBuf) -> SandboxConfig {
+        SandboxConfig {
+            api_url: "http://sandbox:8090".to_string(),
+            api_token: Some("secret-token".to_string()),
+            submit: SubmitMode::Sample,
+            max_sample_bytes: 1 << 20,
+            max_report_bytes: 1 << 20,
+            poll_interval: Duration::from_secs(30),
+            timeout_secs: 3600,
+            malicious_score: 7.0,
+            suspicious_score: 4.0,
+            spool_dir,
+        }
+    }
+
+    fn submission() -> SandboxSubmission {
+        SandboxSubmission {
+            submission_id: Uuid::new_v4(),
+            sha256: SHA256.to_string(),
+            file_path: Some("/tmp/.x/encryptor".to_string()),
+            file_size: Some(81920),
+            agent_id: Some(Uuid::new_v4()),
+            incident_id: None,
+            source: "agent".to_string(),
+            submitted_by: "linux-agent-01".to_string(),
+            reason: None,
+            mode: "sample".to_string(),
+            status: "submitted".to_string(),
+            queued_at: Utc::now(),
+            submitted_at: Some(Utc::now()),
+            finished_at: None,
+            sandbox_task_id: Some(42),
+            score: None,
+            verdict: None,
+            report_sha256: None,
+            detection_id: None,
+            failure_reason: None,
+        }
+    }
+
+    #[test]
+    fn test_verdict_thresholds_and_modes() {
+        let cfg = config(PathBuf::from("/unused"));
+        assert_eq!(cfg.verdict(0.0), Verdict::Clean);
+        assert_eq!(cfg.verdict(3.9), Verdict::Clean);
+        assert_eq!(cfg.verdict(4.0), Verdict::Suspicious);
+        assert_eq!(cfg.verdict(6.99), Verdict::Suspicious);
+        assert_eq!(cfg.verdict(7.0), Verdict::Malicious);
+        assert_eq!(cfg.verdict(10.0), Verdict::Malicious);
+
+        assert_eq!(SubmitMode::parse(" Sample "), Some(SubmitMode::Sample));
+        assert_eq!(SubmitMode::parse("hash"), Some(SubmitMode::Hash));
+        assert_eq!(SubmitMode::parse("upload"), None);
+
+        // The API token never reaches logs
+        assert!(!format!("{:?}", cfg).contains("secret-token"));
+    }
+
+    #[test]
+    fn test_cuckoo_and_cape_responses() {
+        assert_eq!(sandbox::parse_task_id(&serde_json::json!({"task_id": 17})), Some(17));
+        assert_eq!(sandbox::parse_task_id(&serde_json::json!({"data": {"task_ids": [23, 24]}})), Some(23));
+        assert_eq!(sandbox::parse_task_id(&serde_json::json!({"task_id": 0})), None);
+        assert_eq!(sandbox::parse_task_id(&serde_json::json!({"error": true})), None);
+
+        let tasks = serde_json::json!({"tasks": [{"id": 3}, {"id": 11}, {"id": 7}]});
+        assert_eq!(sandbox::latest_task(&tasks), Some(11));
+        assert_eq!(sandbox::latest_task(&serde_json::json!({"tasks": []})), None);
+
+        assert_eq!(sandbox::parse_score(&serde_json::json!({"info": {"score": 8.4}})), Some(8.4));
+        assert_eq!(sandbox::parse_score(&serde_json::json!({"malscore": 2})), Some(2.0));
+        assert_eq!(sandbox::parse_score(&serde_json::json!({"info": {"score": -1}})), None);
+        assert_eq!(sandbox::parse_score(&serde_json::json!({"info": {}})), None);
+
+        let state = |status: &str| sandbox::task_state(&serde_json::json!({"task": {"status": status}}));
+        assert_eq!(state("reported"), Some(TaskState::Reported));
+        assert_eq!(state("running"), Some(TaskState::Running));
+        assert_eq!(state("completed"), Some(TaskState::Running));
+        assert_eq!(state("failed_analysis"), Some(TaskState::Failed("failed_analysis".to_string())));
+        assert_eq!(sandbox::task_state(&serde_json::json!({})), None);
+    }
+
+    #[test]
+    fn test_sample_and_report_spool() {
+        let dir = TempDir::new().unwrap();
+        assert!(sandbox::is_sha256_hex(SHA256));
+        assert!(!sandbox::is_sha256_hex("../../etc/passwd"));
+
+        sandbox::write_sample(dir.path(), SHA256, b"MZ\x90\x00").unwrap();
+        assert_eq!(std::fs::read(sandbox::sample_path(dir.path(), SHA256)).unwrap(), b"MZ\x90\x00");
+        sandbox::remove_sample(dir.path(), SHA256);
+        assert!(!sandbox::sample_path(dir.path(), SHA256).exists());
+
+        let report = br#"{"info":{"id":42,"score":9.2}}"#;
+        let sub = submission();
+        let manifest = SpoolManifest {
+            submission_id: sub.submission_id,
+            sha256: sub.sha256.clone(),
+            file_path: sub.file_path.clone(),
+            file_size: sub.file_size,
+            agent_id: sub.agent_id,
+            incident_id: None,
+            source: sub.source.clone(),
+            submitted_by: sub.submitted_by.clone(),
+            mode: sub.mode.clone(),
+            sandbox_url: "http://sandbox:8090".to_string(),
+            sandbox_task_id: 42,
+            queued_at: sub.queued_at,
+            reported_at: Utc::now(),
+            score: 9.2,
+            verdict: Verdict::Malicious,
+            detection_id: Some(Uuid::new_v4()),
+            report_file: format!("{}.report", sub.submission_id),
+            report_sha256: "00".repeat(32),
+            report_bytes: report.len() as u64,
+        };
+        sandbox::write_spool(dir.path(), &manifest, report).unwrap();
+        assert_eq!(std::fs::read(dir.path().join(&manifest.report_file)).unwrap(), report);
+        let json = std::fs::read(dir.path().join(format!("{}.json", sub.submission_id))).unwrap();
+        let read_back: SpoolManifest = serde_json::from_slice(&json).unwrap();
+        assert_eq!(read_back, manifest);
+        assert_eq!(serde_json::from_slice::<serde_json::Value>(&json).unwrap()["verdict"], "malicious");
+
+        sandbox::remove_spool(dir.path(), sub.submission_id);
+        assert!(!dir.path().join(&manifest.report_file).exists());
+    }
+
+    #[test]
+    fn test_malicious_verdict_detection() {
+        let cfg = config(PathBuf::from("/unused"));
+        let sub = submission();
+        let detection = sandbox::sandbox_detection(&cfg, &sub, 42, 9.2);
+        assert_eq!(detection.detection_name, sandbox::DETECTION_NAME);
+        assert_eq!(detection.detection_engine, sandbox::DETECTION_ENGINE);
+        assert_eq!(detection.severity, "critical");
+        assert!((detection.confidence - 0.92).abs() < 1e-9);
+        assert_eq!(detection.artifacts["sha256"], SHA256);
+        assert_eq!(detection.artifacts["sandbox_task_id"], 42);
+        assert_eq!(detection.artifacts["agent_id"], sub.agent_id.unwrap().to_string());
+        assert_eq!(detection.deterministic_key.len(), 32);
+
+        // Every verdict on the same file deduplicates to one detection
+        let mut other = submission();
+        other.file_path = None;
+        assert_eq!(sandbox::sandbox_detection(&cfg, &other, 77, 10.0).deterministic_key, detection.deterministic_key);
+    }
+}
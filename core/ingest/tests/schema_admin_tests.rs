@@ -0,0 +1,122 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/schema_admin_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for the /schema probe response - contract version and validation status fallbacks, and the probe against a database where the provenance tables are absent
+
+/*
+ * Schema Probe Tests
+ *
+ * The response tests need no database. The probe test reads a disposable database through an
+ * empty scratch schema, so every provenance table is undefined:
+ *
+ *   RANSOMEYE_TEST_SCHEMA_DB=1 DB_HOST=... DB_PORT=... DB_NAME=... DB_USER=... DB_PASS=... \
+ *       cargo test -p ingest --test schema_admin_tests
+ *
+ * Without RANSOMEYE_TEST_SCHEMA_DB=1 it skips.
+ */
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use ingest::http_schema_admin::{load_schema_status, schema_status, SchemaMigration, SchemaValidation, TableSize};
+    use ingest::schema_compat::SCHEMA_VERSION;
+    use tokio_postgres::NoTls;
+    use uuid::Uuid;
+
+    fn migration(contract: &str, kind: &str, day: u32) -> SchemaMigration {
+        SchemaMigration {
+            migration_id: Uuid::new_v4(),
+            contract_sha256: contract.repeat(64),
+            kind: kind.to_string(),
+            tables_applied: Vec::new(),
+            applied_at: Utc.with_ymd_and_hms(2026, 1, day, 0, 0, 0).unwrap(),
+        }
+    }
+
+    fn validation(contract: &str, status: &str) -> SchemaValidation {
+        SchemaValidation {
+            contract_sha256: contract.repeat(64),
+            status: status.to_string(),
+            detail: None,
+            validated_at: Utc.with_ymd_and_hms(2026, 1, 3, 0, 0, 0).unwrap(),
+        }
+    }
+
+    fn table(name: &str, total_bytes: i64) -> TableSize {
+        TableSize { table: name.to_string(), estimated_rows: 0, total_bytes }
+    }
+
+    #[test]
+    fn test_nothing_recorded_reports_unknown() {
+        let status = schema_status(None, Vec::new(), Vec::new(), None, Vec::new());
+        assert_eq!(status.validation_status, "unknown");
+        assert_eq!(status.contract_sha256, None);
+        assert!(status.last_validation.is_none() && status.migrations.is_empty());
+        assert_eq!(status.total_bytes, 0);
+        assert_eq!(status.code_schema_version, SCHEMA_VERSION);
+        assert_eq!(status.schema_generation, None);
+    }
+
+    #[test]
+    fn test_contract_falls_back_to_newest_migration() {
+        // Newest first, as the probe reads them
+        let migrations = vec![migration("b", "incremental", 2), migration("a", "full", 1)];
+        let status = schema_status(None, migrations, vec![table("raw_events", 10), table("alerts", 5)], Some(1), Vec::new());
+        assert_eq!(status.contract_sha256, Some("b".repeat(64)));
+        assert_eq!(status.validation_status, "unknown");
+        assert_eq!(status.migrations.len(), 2);
+        assert_eq!(status.total_bytes, 15);
+        assert_eq!(status.schema_generation, Some(1));
+    }
+
+    #[test]
+    fn test_validation_wins_over_migration() {
+        let status = schema_status(
+            Some(validation("c", "failed")),
+            vec![migration("b", "incremental", 2)],
+            Vec::new(),
+            None,
+            vec!["alerts.severity->alert_severity".to_string()],
+        );
+        assert_eq!(status.contract_sha256, Some("c".repeat(64)));
+        assert_eq!(status.validation_status, "failed");
+        assert_eq!(status.renamed_columns, vec!["alerts.severity->alert_severity".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_probe_without_provenance_tables() {
+        if std::env::var("RANSOMEYE_TEST_SCHEMA_DB").as_deref() != Ok("1") {
+            eprintln!("skipping: RANSOMEYE_TEST_SCHEMA_DB != 1");
+            return;
+        }
+        let env = |k: &str| std::env::var(k).unwrap_or_else(|_| panic!("{k} not set"));
+        let (client, connection) = tokio_postgres::connect(
+            &format!(
+                "host={} port={} dbname={} user={} password={}",
+                env("DB_HOST"),
+                env("DB_PORT"),
+                env("DB_NAME"),
+                env("DB_USER"),
+                env("DB_PASS")
+            ),
+            NoTls,
+        )
+        .await
+        .expect("connect");
+        tokio::spawn(connection);
+
+        let scratch = format!("schema_probe_{}", Uuid::new_v4().simple());
+        client
+            .batch_execute(&format!("CREATE SCHEMA {scratch}; SET search_path = {scratch};"))
+            .await
+            .expect("scratch schema");
+        let status = load_schema_status(&client).await;
+        client.batch_execute(&format!("DROP SCHEMA {scratch}")).await.expect("drop scratch schema");
+
+        let status = status.expect("undefined provenance tables are not an error");
+        assert_eq!(status.validation_status, "unknown");
+        assert_eq!(status.contract_sha256, None);
+        assert!(status.migrations.is_empty() && status.renamed_columns.is_empty());
+        assert_eq!(status.schema_generation, None);
+        assert_eq!(status.total_bytes, status.tables.iter().map(|t| t.total_bytes).sum::<i64>());
+    }
+}
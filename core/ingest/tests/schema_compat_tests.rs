@@ -0,0 +1,85 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/schema_compat_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for rolling-upgrade schema compatibility - the generation matrix, the declared-column parse of the authoritative schema and unknown-column classification
+
+/*
+ * Schema Compatibility Tests
+ *
+ * A build runs against its own generation or one generation newer, never older or further ahead,
+ * and never below a generation's min_code_version; columns the build does not know are tolerated
+ * unless they are NOT NULL without a default on a table the service writes.
+ */
+
+#[cfg(test)]
+mod tests {
+    use ingest::schema_compat::{
+        check_compatibility, declared_columns, retirable_renames, unknown_columns, ColumnRename, Compatibility,
+        LiveColumn, SchemaGeneration, AUTHORITATIVE_SCHEMA, INGEST_WRITTEN_TABLES,
+    };
+
+    fn generation(version: i32, min_code_version: i32) -> SchemaGeneration {
+        SchemaGeneration { version, min_code_version, description: "test".to_string() }
+    }
+
+    fn live(table: &str, column: &str, not_null: bool, has_default: bool) -> LiveColumn {
+        LiveColumn { table: table.to_string(), column: column.to_string(), not_null, has_default }
+    }
+
+    #[test]
+    fn test_compatibility_matrix() {
+        assert_eq!(check_compatibility(3, &generation(3, 1)), Ok(Compatibility::Current));
+        assert_eq!(check_compatibility(3, &generation(4, 3)), Ok(Compatibility::SchemaAhead { schema_version: 4 }));
+        assert!(check_compatibility(3, &generation(2, 1)).unwrap_err().contains("older"));
+        assert!(check_compatibility(3, &generation(5, 1)).unwrap_err().contains("skew"));
+        assert!(check_compatibility(3, &generation(4, 4)).unwrap_err().contains("requires code"));
+    }
+
+    #[test]
+    fn test_authoritative_schema_declares_compat_tables() {
+        let declared = declared_columns(AUTHORITATIVE_SCHEMA);
+        let versions = declared.get("schema_versions").expect("schema_versions declared");
+        assert!(versions.contains("schema_version") && versions.contains("min_code_version"));
+        assert!(declared.get("components").expect("components declared").contains("build_metadata"));
+        for table in INGEST_WRITTEN_TABLES {
+            assert!(declared.contains_key(*table), "{table} is not in the authoritative schema");
+        }
+        // Table constraints are not columns
+        assert!(!declared["schema_column_renames"].contains("primary"));
+    }
+
+    #[test]
+    fn test_unknown_columns_tolerated_unless_required() {
+        let declared = declared_columns(AUTHORITATIVE_SCHEMA);
+        let live_cols = vec![
+            live("components", "build_metadata", false, false),
+            live("components", "region", false, false),
+            live("components", "tier", true, true),
+            live("components", "owner_id", true, false),
+            // Not written by this service
+            live("schema_versions", "checksum", true, false),
+        ];
+        let unknown = unknown_columns(&declared, &live_cols, &["components"]);
+        assert_eq!(unknown.tolerated, vec!["components.region", "components.tier"]);
+        assert_eq!(unknown.blocking, vec!["components.owner_id"]);
+    }
+
+    #[test]
+    fn test_renames_retirable_after_one_release() {
+        let renames = vec![
+            ColumnRename {
+                table: "agents".to_string(),
+                old_column: "hostname".to_string(),
+                new_column: "host_name".to_string(),
+                since_version: 2,
+            },
+            ColumnRename {
+                table: "agents".to_string(),
+                old_column: "os".to_string(),
+                new_column: "os_family".to_string(),
+                since_version: 3,
+            },
+        ];
+        let due: Vec<&str> = retirable_renames(&renames, 3).iter().map(|r| r.old_column.as_str()).collect();
+        assert_eq!(due, vec!["hostname"]);
+    }
+}
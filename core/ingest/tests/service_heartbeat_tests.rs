@@ -0,0 +1,206 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/service_heartbeat_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for the orchestrator heartbeat protocol - heartbeat validation, the service-side orchestrator link (connect, loss, recovery, restart) and the health report sent with heartbeats
+
+/*
+ * Service Heartbeat Tests
+ *
+ * Tests that malformed heartbeats are rejected and that the link reports the orchestrator
+ * lost exactly once per outage, recovered on the next acknowledgement, and restarted when the
+ * orchestrator instance changes, and that the ingest health report degrades with the link and
+ * while load shedding, and turns unhealthy once draining.
+ */
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use chrono::Utc;
+    use health::HealthStatus;
+    use uuid::Uuid;
+
+    use ingest::dashboard_cache::DashboardCache;
+    use ingest::disk_quota::DiskQuotas;
+    use ingest::handoff::DrainController;
+    use ingest::load_shedding::{LoadShedConfig, LoadShedMode, LoadShedder, ShedPriority};
+    use ingest::pipeline::{PipelineLoad, PipelineStats, ShardStats};
+    use ingest::service_health;
+    use ingest::service_heartbeat::{
+        HeartbeatAck, HeartbeatConfig, LinkEvent, OrchestratorLink, ServiceStatus, INGEST_SERVICE_NAME,
+    };
+
+    fn cfg() -> HeartbeatConfig {
+        HeartbeatConfig {
+            orchestrator_url: "http://127.0.0.1:8091".to_string(),
+            token: None,
+            interval: Duration::from_secs(10),
+            lost_after: Duration::from_secs(30),
+            request_timeout: Duration::from_secs(5),
+        }
+    }
+
+    fn ack(instance: Uuid) -> HeartbeatAck {
+        HeartbeatAck {
+            orchestrator_instance: instance,
+            orchestrator_state: "RUNNING".to_string(),
+            orchestrator_healthy: true,
+            required: true,
+            received_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_heartbeat_validation() {
+        let link = OrchestratorLink::new(INGEST_SERVICE_NAME, "host-a", Some(&cfg()));
+        let hb = link.heartbeat(ServiceStatus::Ready, Duration::from_secs(10), None);
+        assert!(hb.validate().is_ok());
+        assert_eq!(hb.boot_id, link.boot_id());
+
+        let mut bad = hb.clone();
+        bad.service = "Ingest Server".to_string();
+        assert!(bad.validate().is_err());
+        let mut bad = hb.clone();
+        bad.instance_id.clear();
+        assert!(bad.validate().is_err());
+        let mut bad = hb;
+        bad.interval_secs = 0;
+        assert!(bad.validate().is_err());
+    }
+
+    #[test]
+    fn test_link_lost_once_then_recovered() {
+        let link = OrchestratorLink::new(INGEST_SERVICE_NAME, "host-a", Some(&cfg()));
+        let orch = Uuid::new_v4();
+        let t0 = Instant::now();
+
+        assert_eq!(link.record_ack(ack(orch), t0), Some(LinkEvent::Connected { orchestrator_instance: orch }));
+        assert_eq!(link.record_ack(ack(orch), t0 + Duration::from_secs(10)), None);
+
+        assert_eq!(link.record_failure("HTTP 503", t0 + Duration::from_secs(20)), None);
+        assert_eq!(
+            link.record_failure("HTTP 503", t0 + Duration::from_secs(40)),
+            Some(LinkEvent::Lost { silent_secs: 30 })
+        );
+        assert_eq!(link.record_failure("HTTP 503", t0 + Duration::from_secs(50)), None);
+
+        let status = link.status(t0 + Duration::from_secs(50));
+        assert!(!status.connected);
+        assert_eq!(status.consecutive_failures, 3);
+        assert_eq!(status.last_error.as_deref(), Some("HTTP 503"));
+
+        assert_eq!(
+            link.record_ack(ack(orch), t0 + Duration::from_secs(60)),
+            Some(LinkEvent::Recovered { orchestrator_instance: orch })
+        );
+        let status = link.status(t0 + Duration::from_secs(61));
+        assert!(status.connected);
+        assert_eq!(status.consecutive_failures, 0);
+        assert_eq!(status.orchestrator_state.as_deref(), Some("RUNNING"));
+    }
+
+    #[test]
+    fn test_orchestrator_restart_detected() {
+        let link = OrchestratorLink::new(INGEST_SERVICE_NAME, "host-a", Some(&cfg()));
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        let t0 = Instant::now();
+        link.record_ack(ack(first), t0);
+        assert_eq!(
+            link.record_ack(ack(second), t0 + Duration::from_secs(10)),
+            Some(LinkEvent::Restarted { previous: first, current: second })
+        );
+    }
+
+    #[test]
+    fn test_disabled_link() {
+        let link = OrchestratorLink::new(INGEST_SERVICE_NAME, "host-a", None);
+        let status = link.status(Instant::now());
+        assert!(!status.enabled);
+        assert!(!status.connected);
+        assert_eq!(status.orchestrator_url, None);
+    }
+
+    #[test]
+    fn test_health_report_follows_link_and_drain() {
+        let link = OrchestratorLink::new(INGEST_SERVICE_NAME, "host-a", Some(&cfg()));
+        let drain = DrainController::new();
+        let pipeline = PipelineStats {
+            shards: 2,
+            queue_depth: 4,
+            backlog_age_secs: 300,
+            realtime_ratio: 4,
+            started: true,
+            latency_us: 300_000,
+            per_shard: vec![
+                ShardStats {
+                    shard: 0,
+                    queued: 1,
+                    backlog_queued: 1,
+                    processed: 10,
+                    backlog_processed: 0,
+                    refused: 0,
+                    latency_us: 300_000,
+                },
+                ShardStats {
+                    shard: 1,
+                    queued: 0,
+                    backlog_queued: 0,
+                    processed: 5,
+                    backlog_processed: 3,
+                    refused: 2,
+                    latency_us: 0,
+                },
+            ],
+        };
+        let shedder = LoadShedder::new(LoadShedConfig::off());
+        let shed = shedder.stats();
+        let quotas = DiskQuotas::disabled();
+        let dashboard = DashboardCache::new(Duration::from_secs(15));
+        let t0 = Instant::now();
+
+        // Not acknowledged yet: serving, degraded
+        let report = service_health::report(&drain, &pipeline, &shed, &quotas, &dashboard, &link.status(t0));
+        assert_eq!(report.component, INGEST_SERVICE_NAME);
+        assert_eq!(report.instance_id, "host-a");
+        assert_eq!(report.status, HealthStatus::Degraded);
+        assert_eq!(report.subsystem("orchestrator_link").unwrap().status, HealthStatus::Degraded);
+        assert_eq!(report.metrics["pipeline_processed"], 15.0);
+        assert_eq!(report.metrics["pipeline_refused"], 2.0);
+        assert_eq!(report.metrics["pipeline_backlog_queued"], 1.0);
+
+        link.record_ack(ack(Uuid::new_v4()), t0);
+        let report = service_health::report(&drain, &pipeline, &shed, &quotas, &dashboard, &link.status(t0));
+        assert_eq!(report.status, HealthStatus::Healthy);
+        assert_eq!(report.status_details(), None);
+
+        let mut full = pipeline.clone();
+        full.per_shard[1].queued = 4;
+        let report = service_health::report(&drain, &full, &shed, &quotas, &dashboard, &link.status(t0));
+        assert_eq!(report.status, HealthStatus::Degraded);
+        assert_eq!(report.status_details().as_deref(), Some("pipeline: degraded (1 of 2 shard queue(s) full)"));
+
+        // Database slow: shedding degrades the report until it stops
+        let shedder = LoadShedder::new(LoadShedConfig { mode: LoadShedMode::Reject, ..LoadShedConfig::off() });
+        let load = PipelineLoad {
+            shard_queued: 1,
+            queued: 1,
+            queue_depth: 4,
+            shards: 2,
+            latency: Duration::from_millis(300),
+            shard_latency: Duration::from_millis(300),
+        };
+        shedder.admit(ShedPriority::Low, &load);
+        let report = service_health::report(&drain, &pipeline, &shedder.stats(), &quotas, &dashboard, &link.status(t0));
+        assert_eq!(report.status, HealthStatus::Degraded);
+        assert_eq!(
+            report.status_details().as_deref(),
+            Some("load_shedding: degraded (shedding (reject): average unit of work 300ms)")
+        );
+        assert_eq!(report.metrics["load_shed_rejected"], 1.0);
+
+        drain.begin("SIGTERM");
+        let report = service_health::report(&drain, &pipeline, &shed, &quotas, &dashboard, &link.status(t0));
+        assert_eq!(report.status, HealthStatus::Unhealthy);
+        assert!(!report.status.is_serving());
+        assert_eq!(report.subsystem("drain").unwrap().detail.as_deref(), Some("draining: SIGTERM"));
+    }
+}
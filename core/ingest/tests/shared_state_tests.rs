@@ -0,0 +1,94 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/shared_state_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for shared ingest state - replay verdicts of the state backend, scope separation and replay/ordering checks running on one backend
+
+/*
+ * Shared Ingest State Tests
+ *
+ * The postgres backend follows the verdicts of the local one (same contract, different storage);
+ * these tests pin that contract: duplicate nonces and sequence/timestamp regressions are refused
+ * without moving the producer forward, the previously accepted sequence is reported, and scopes
+ * never see each other's state.
+ */
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::{Duration, Utc};
+
+    use ingest::security::replay_protection::{ReplayProtector, AUTH_REPLAY_SCOPE};
+    use ingest::shared_state::{LocalReplayState, ReplayStateBackend, ReplayVerdict, SharedStateMode};
+
+    #[tokio::test]
+    async fn test_accept_reports_previous_sequence() {
+        let state = LocalReplayState::new();
+        let now = Utc::now();
+        assert_eq!(
+            state.record("auth", "p1", "n1", 5, now).await.unwrap(),
+            ReplayVerdict::Accepted { previous_sequence: None }
+        );
+        assert_eq!(
+            state.record("auth", "p1", "n2", 9, now).await.unwrap(),
+            ReplayVerdict::Accepted { previous_sequence: Some(5) }
+        );
+        assert_eq!(state.last_sequence_number("auth", "p1").await.unwrap(), Some(9));
+        assert_eq!(state.mode(), SharedStateMode::Local);
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_nonce_refused() {
+        let state = LocalReplayState::new();
+        let now = Utc::now();
+        state.record("auth", "p1", "n1", 1, now).await.unwrap();
+        assert_eq!(state.record("auth", "p1", "n1", 2, now).await.unwrap(), ReplayVerdict::DuplicateNonce);
+        assert_eq!(state.last_sequence_number("auth", "p1").await.unwrap(), Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_regressions_refused_without_advancing() {
+        let state = LocalReplayState::new();
+        let now = Utc::now();
+        state.record("auth", "p1", "n1", 10, now).await.unwrap();
+        assert_eq!(
+            state.record("auth", "p1", "n2", 9, now).await.unwrap(),
+            ReplayVerdict::SequenceRegression { last_sequence: 10 }
+        );
+        assert_eq!(
+            state.record("auth", "p1", "n3", 11, now - Duration::seconds(30)).await.unwrap(),
+            ReplayVerdict::TimestampRegression { last_timestamp: now }
+        );
+        // Refused nonces are not claimed
+        assert_eq!(
+            state.record("auth", "p1", "n2", 11, now).await.unwrap(),
+            ReplayVerdict::Accepted { previous_sequence: Some(10) }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_scopes_are_independent() {
+        let state = LocalReplayState::new();
+        let now = Utc::now();
+        state.record("auth", "p1", "n1", 7, now).await.unwrap();
+        assert_eq!(
+            state.record("ordering", "p1", "n1", 7, now).await.unwrap(),
+            ReplayVerdict::Accepted { previous_sequence: None }
+        );
+        assert_eq!(state.last_sequence_number("ordering", "p2").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_protectors_on_one_backend_share_state() {
+        // Two instances on the same backend: an event accepted by one is a replay on the other
+        let state: Arc<dyn ReplayStateBackend> = Arc::new(LocalReplayState::new());
+        let first = ReplayProtector::with_state(state.clone(), AUTH_REPLAY_SCOPE);
+        let second = ReplayProtector::with_state(state, AUTH_REPLAY_SCOPE);
+        let now = Utc::now();
+
+        assert_eq!(first.check_replay_sequence("agent-1", "n1", &now, 1).await.unwrap(), None);
+        assert!(second.check_replay("agent-1", "n1", &now, 2).await.is_err());
+        assert!(second.check_replay("agent-1", "n2", &now, 0).await.is_err());
+        assert_eq!(second.check_replay_sequence("agent-1", "n3", &now, 2).await.unwrap(), Some(1));
+        assert_eq!(first.get_last_sequence_number("agent-1").await, Some(2));
+    }
+}
@@ -0,0 +1,202 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/signature_verification_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for event signature verification - Ed25519 over sequence || payload hash against trust store keys, payload hash bound to the received envelope, unknown signers and key file formats
+
+/*
+ * Event Signature Verification Tests
+ *
+ * A signed event verifies only against the key registered for its signer, over the exact
+ * sequence and payload hash it was signed with, and that hash must be the SHA-256 of the
+ * envelope actually received: /ingest/linux refuses a valid signature carried over a swapped
+ * envelope. Unknown signers have no key and are reported as such; trust store key files may
+ * hold the raw key or its hex.
+ */
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use axum::body::Body;
+    use axum::http::{header, Request, StatusCode};
+    use base64::{engine::general_purpose::STANDARD, Engine as _};
+    use crypto::signature::Ed25519KeyPair;
+    use tempfile::TempDir;
+    use tower::Service;
+    use uuid::Uuid;
+
+    use envelope_fixtures::{Golden, FIXTURE_SIGNER_ID};
+    use ingest::http_server::HttpIngestionServer;
+    use ingest::runtime_controls::{RuntimeControlConfig, RuntimeControls};
+    use ingest::signature_verification::{
+        self, EventSignatureVerifier, KeySource, SignatureMode, SignatureStatus,
+    };
+    use ingest::storage::SqliteStore;
+
+    /// Envelope bytes of HASH
+    const ENVELOPE: &[u8] = b"test";
+    const HASH: &str = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+
+    fn key_pair(seed: u8) -> Ed25519KeyPair {
+        Ed25519KeyPair::from_seed(&[seed; 32]).unwrap()
+    }
+
+    fn sign(key: &Ed25519KeyPair, sequence: Option<u64>, hash: &str) -> Vec<u8> {
+        key.sign(&signature_verification::signed_message(sequence, hash).unwrap()).to_vec()
+    }
+
+    fn verifier(signers: &[(&str, &Ed25519KeyPair)]) -> EventSignatureVerifier {
+        let trust_store: HashMap<_, _> = signers.iter().map(|(id, k)| (id.to_string(), k.public_key())).collect();
+        EventSignatureVerifier::new(SignatureMode::Enforce, trust_store, None)
+    }
+
+    #[tokio::test]
+    async fn test_signature_verifies_over_sequence_and_hash() {
+        let key = key_pair(7);
+        let verifier = verifier(&[("ws-0142", &key)]);
+        let agent = Uuid::new_v4();
+
+        let check = verifier.verify(agent, "ws-0142", Some(42), HASH, ENVELOPE, &sign(&key, Some(42), HASH)).await.unwrap();
+        assert!(check.is_valid());
+        assert_eq!(check.key_source, Some(KeySource::TrustStore));
+
+        // No sequence: signed over the hash bytes alone
+        let check = verifier.verify(agent, "ws-0142", None, HASH, ENVELOPE, &sign(&key, None, HASH)).await.unwrap();
+        assert!(check.is_valid());
+    }
+
+    #[tokio::test]
+    async fn test_tampered_or_foreign_signature_is_invalid() {
+        let key = key_pair(7);
+        let verifier = verifier(&[("ws-0142", &key)]);
+        let agent = Uuid::new_v4();
+        let signature = sign(&key, Some(42), HASH);
+
+        // Replayed under another sequence
+        let check = verifier.verify(agent, "ws-0142", Some(43), HASH, ENVELOPE, &signature).await.unwrap();
+        assert_eq!(check.status, SignatureStatus::Invalid);
+
+        // Different payload hash
+        let other = HASH.replace('9', "8");
+        let check = verifier.verify(agent, "ws-0142", Some(42), &other, ENVELOPE, &signature).await.unwrap();
+        assert_eq!(check.status, SignatureStatus::Invalid);
+
+        // Signed by another key under the right signer_id
+        let check = verifier.verify(agent, "ws-0142", Some(42), HASH, ENVELOPE, &sign(&key_pair(8), Some(42), HASH)).await.unwrap();
+        assert_eq!(check.status, SignatureStatus::Invalid);
+
+        // Truncated signature and a payload_hash that is not hex
+        let check = verifier.verify(agent, "ws-0142", Some(42), HASH, ENVELOPE, &signature[..32]).await.unwrap();
+        assert!(check.error.unwrap().contains("32 bytes"));
+        let check = verifier.verify(agent, "ws-0142", Some(42), "zz", ENVELOPE, &signature).await.unwrap();
+        assert_eq!(check.status, SignatureStatus::Invalid);
+    }
+
+    #[tokio::test]
+    async fn test_payload_hash_bound_to_received_envelope() {
+        let key = key_pair(7);
+        let verifier = verifier(&[("ws-0142", &key)]);
+
+        // A valid signature over HASH, presented with an envelope HASH does not cover
+        let check = verifier
+            .verify(Uuid::new_v4(), "ws-0142", Some(42), HASH, b"tesT", &sign(&key, Some(42), HASH))
+            .await
+            .unwrap();
+        assert_eq!(check.status, SignatureStatus::Invalid);
+        assert_eq!(check.key_source, None);
+        assert!(check.error.unwrap().contains("does not match the received envelope"));
+
+        assert!(signature_verification::check_payload_hash(HASH, ENVELOPE).is_ok());
+        assert!(signature_verification::check_payload_hash(&HASH.to_uppercase(), ENVELOPE).is_ok());
+        assert!(signature_verification::check_payload_hash("zz", ENVELOPE).is_err());
+    }
+
+    /// Ingest server on a SQLite store in `dir`, agent tokens and key pins off, `key` registered
+    /// for the fixture signer in a trust store under `dir`.
+    fn ingest_app(dir: &TempDir, key: &Ed25519KeyPair) -> axum::Router {
+        let agent_keys = dir.path().join("agent_keys");
+        std::fs::create_dir_all(&agent_keys).unwrap();
+        std::fs::write(agent_keys.join(format!("{}.pub", FIXTURE_SIGNER_ID)), key.public_key()).unwrap();
+        std::env::set_var("RANSOMEYE_TRUST_STORE_PATH", dir.path());
+        std::env::set_var("RANSOMEYE_INGEST_AUTH_MODE", "disabled");
+        std::env::set_var("RANSOMEYE_INGEST_KEY_PINNING", "disabled");
+        std::env::set_var("RANSOMEYE_INGEST_SIGNATURE_VERIFICATION", "enforce");
+
+        let controls = RuntimeControls::new(None, "info", RuntimeControlConfig::from_env().unwrap()).unwrap();
+        let store = Arc::new(SqliteStore::open(&dir.path().join("ingest.db")).unwrap());
+        HttpIngestionServer::with_store("127.0.0.1:0".to_string(), Arc::new(controls), store, None)
+            .unwrap()
+            .router()
+    }
+
+    /// SignedEvent carrying `envelope` under the payload_hash and signature of `signed`
+    fn signed_event(key: &Ed25519KeyPair, signed: &Golden, envelope: &Golden) -> Request<Body> {
+        let signature = STANDARD.encode(sign(key, Some(1), &signed.payload_hash()));
+        let body = format!(
+            r#"{{"envelope":{},"payload_hash":"{}","signature":"{}","signer_id":"{}","sequence":1}}"#,
+            envelope.envelope,
+            signed.payload_hash(),
+            signature,
+            FIXTURE_SIGNER_ID
+        );
+        Request::post("/ingest/linux").header(header::CONTENT_TYPE, "application/json").body(Body::from(body)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_ingest_rejects_swapped_envelope_under_valid_signature() {
+        let key = key_pair(7);
+        let dir = TempDir::new().unwrap();
+        let mut app = ingest_app(&dir, &key);
+        let goldens = envelope_fixtures::all();
+        let (signed, swapped) = (&goldens[0], &goldens[1]);
+
+        // The signature is valid for the first envelope's payload_hash, but the body carries another
+        let response = app.call(signed_event(&key, signed, swapped)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        // Rejected for the payload binding, not the signature, and recorded as such
+        let conn = rusqlite::Connection::open(dir.path().join("ingest.db")).unwrap();
+        let (status, error): (String, String) = conn
+            .query_row("SELECT signature_status, error_details FROM signature_validation_events", [], |r| Ok((r.get(0)?, r.get(1)?)))
+            .unwrap();
+        assert_eq!(status, "invalid");
+        assert_eq!(error, "payload_hash does not match the received envelope");
+    }
+
+    #[tokio::test]
+    async fn test_unknown_signer_has_no_key() {
+        let key = key_pair(7);
+        let verifier = verifier(&[("ws-0142", &key)]);
+        let check = verifier.verify(Uuid::new_v4(), "ws-9999", Some(1), HASH, ENVELOPE, &sign(&key, Some(1), HASH)).await.unwrap();
+        assert_eq!(check.status, SignatureStatus::Unknown);
+        assert_eq!(check.key_source, None);
+        assert_eq!(check.status.as_str(), "unknown");
+    }
+
+    #[test]
+    fn test_trust_store_reads_raw_and_hex_key_files() {
+        let dir = TempDir::new().unwrap();
+        let (raw, hexed) = (key_pair(1), key_pair(2));
+        std::fs::write(dir.path().join("ws-0001.pub"), raw.public_key()).unwrap();
+        std::fs::write(dir.path().join("ws-0002.pub"), format!("{}\n", hex::encode(hexed.public_key()))).unwrap();
+        std::fs::write(dir.path().join("README"), "not a key").unwrap();
+
+        let keys = signature_verification::load_trust_store(dir.path()).unwrap();
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys["ws-0001"], raw.public_key());
+        assert_eq!(keys["ws-0002"], hexed.public_key());
+
+        // Missing directory: empty trust store; malformed key file: startup error
+        assert!(signature_verification::load_trust_store(&dir.path().join("absent")).unwrap().is_empty());
+        std::fs::write(dir.path().join("ws-0003.pub"), "0011").unwrap();
+        assert!(signature_verification::load_trust_store(dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_mode_parse() {
+        assert_eq!(SignatureMode::parse("enforce").unwrap(), SignatureMode::Enforce);
+        assert_eq!(SignatureMode::parse("detect").unwrap(), SignatureMode::Detect);
+        assert_eq!(SignatureMode::parse("disabled").unwrap().as_str(), "disabled");
+        assert!(SignatureMode::parse("off").is_err());
+    }
+}
@@ -0,0 +1,345 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/storage_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for the pluggable telemetry store - SQLite lab backend round trips, unit-of-work atomicity, and audit hash chaining
+
+/*
+ * Storage Tests
+ *
+ * Exercise the TelemetryStore contract on the SQLite backend (no Postgres needed):
+ * committed units of work are queryable, rolled-back and dropped ones leave nothing behind,
+ * and audit rows chain SHA256(prev_chain_hash || payload_sha256).
+ */
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use serde_json::json;
+    use sha2::{Digest, Sha256};
+    use uuid::Uuid;
+
+    use ingest::storage::{
+        self, AuditRecord, ConflictResolution, DetectionRecord, IdentityConflictRecord, IdentityOrigin, LinuxTelemetry,
+        PayloadStorage, QuarantinedEventRecord, RawEventRecord, SqliteStore, StorageBackend, TelemetryProvenance,
+        TelemetryQuery, TelemetryRecord, TelemetrySource, TelemetryStore,
+    };
+
+    fn raw(agent_id: Uuid, source: TelemetrySource, minutes_ago: i64) -> RawEventRecord {
+        let payload = json!({ "event": "test", "minutes_ago": minutes_ago });
+        RawEventRecord {
+            source,
+            agent_id,
+            observed_at: Utc::now() - Duration::minutes(minutes_ago),
+            event_name: "process_start".to_string(),
+            payload_sha256: Sha256::digest(payload.to_string().as_bytes()).to_vec(),
+            payload_json: serde_json::value::to_raw_value(&payload).unwrap(),
+            payload_storage: PayloadStorage::Full,
+            payload_storage_reason: "policy:full".to_string(),
+            residency_region: None,
+            zone: None,
+        }
+    }
+
+    fn linux(agent_id: Uuid) -> TelemetryRecord {
+        TelemetryRecord::Linux(LinuxTelemetry {
+            provenance: TelemetryProvenance {
+                agent_id,
+                message_id: Uuid::new_v4(),
+                nonce: "00".repeat(32),
+                component_identity: "lab-host-1".to_string(),
+                signature_b64: "c2ln".to_string(),
+                signature_alg: "Ed25519".to_string(),
+                data_hash_hex: "ab".repeat(32),
+                observed_at: Utc::now(),
+            },
+            host_id: "lab-host-1".to_string(),
+            event_name: "process_start".to_string(),
+            event_category: "process_start".to_string(),
+            pid: Some(42),
+            uid: Some(0),
+            process_name: Some("/usr/bin/true".to_string()),
+            cmdline: None,
+            file_path: None,
+            network_src_ip: None,
+            network_dst_ip: None,
+            network_src_port: None,
+            network_dst_port: None,
+            netns_inode: None,
+            host_netns: None,
+            protocol: None,
+            container_id: None,
+            container_image: None,
+            pod_name: None,
+            pod_namespace: None,
+            payload_json: "{}".to_string(),
+            payload_sha256: None,
+        })
+    }
+
+    fn audit(action: &str) -> AuditRecord {
+        let payload = json!({ "action": action });
+        AuditRecord {
+            actor_component_id: None,
+            actor_agent_id: None,
+            action: action.to_string(),
+            object_type: "raw_event".to_string(),
+            object_id: None,
+            event_time: Some(Utc::now()),
+            payload_sha256: Sha256::digest(payload.to_string().as_bytes()).to_vec(),
+            payload_json: payload,
+        }
+    }
+
+    fn all() -> TelemetryQuery {
+        TelemetryQuery { limit: 100, ..Default::default() }
+    }
+
+    #[tokio::test]
+    async fn test_committed_unit_of_work_is_queryable() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        assert_eq!(store.backend(), StorageBackend::Sqlite);
+        let agent_id = store.resolve_agent("lab-host-1", TelemetrySource::LinuxAgent).await.unwrap();
+
+        let mut tx = store.begin().await.unwrap();
+        tx.append_audit(&audit("INGEST_ACCEPT")).await.unwrap();
+        let raw_event_id = tx.insert_raw(&raw(agent_id, TelemetrySource::LinuxAgent, 0)).await.unwrap();
+        tx.insert_telemetry(&linux(agent_id)).await.unwrap();
+        tx.commit().await.unwrap();
+
+        let events = store.query(&all()).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].raw_event_id, raw_event_id);
+        assert_eq!(events[0].agent_id, agent_id);
+        assert_eq!(events[0].payload_json["event"], "test");
+        assert_eq!(events[0].payload_storage, PayloadStorage::Full);
+    }
+
+    #[tokio::test]
+    async fn test_summary_payload_storage_round_trips() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let agent_id = store.resolve_agent("lab-host-1", TelemetrySource::LinuxAgent).await.unwrap();
+
+        let mut record = raw(agent_id, TelemetrySource::LinuxAgent, 0);
+        record.payload_json = serde_json::value::to_raw_value(&json!({ "payload_storage": "summary" })).unwrap();
+        record.payload_storage = PayloadStorage::Summary;
+        record.payload_storage_reason = "routine".to_string();
+
+        let mut tx = store.begin().await.unwrap();
+        tx.insert_raw(&record).await.unwrap();
+        tx.commit().await.unwrap();
+
+        let events = store.query(&all()).await.unwrap();
+        assert_eq!(events[0].payload_storage, PayloadStorage::Summary);
+        assert_eq!(events[0].payload_json["payload_storage"], "summary");
+    }
+
+    #[tokio::test]
+    async fn test_rollback_and_drop_discard_writes() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let agent_id = store.resolve_agent("lab-host-1", TelemetrySource::LinuxAgent).await.unwrap();
+
+        let mut tx = store.begin().await.unwrap();
+        tx.insert_raw(&raw(agent_id, TelemetrySource::LinuxAgent, 0)).await.unwrap();
+        tx.rollback().await;
+
+        {
+            let mut tx = store.begin().await.unwrap();
+            tx.insert_raw(&raw(agent_id, TelemetrySource::LinuxAgent, 0)).await.unwrap();
+            // Dropped without commit
+        }
+
+        assert!(store.query(&all()).await.unwrap().is_empty());
+        // Connection is usable again after the abandoned unit of work
+        let tx = store.begin().await.unwrap();
+        tx.commit().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_message_id_fails_unit_of_work() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let agent_id = store.resolve_agent("lab-host-1", TelemetrySource::LinuxAgent).await.unwrap();
+        let record = linux(agent_id);
+
+        let mut tx = store.begin().await.unwrap();
+        tx.insert_telemetry(&record).await.unwrap();
+        tx.commit().await.unwrap();
+
+        let mut tx = store.begin().await.unwrap();
+        tx.insert_raw(&raw(agent_id, TelemetrySource::LinuxAgent, 0)).await.unwrap();
+        assert!(tx.insert_telemetry(&record).await.is_err());
+        tx.rollback().await;
+
+        // raw_events row from the failed unit of work must not survive
+        assert!(store.query(&all()).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_agent_is_stable_per_identity_and_source() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let a = store.resolve_agent("host-a", TelemetrySource::LinuxAgent).await.unwrap();
+        let b = store.resolve_agent("host-a", TelemetrySource::LinuxAgent).await.unwrap();
+        let c = store.resolve_agent("host-a", TelemetrySource::DpiProbe).await.unwrap();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[tokio::test]
+    async fn test_query_filters_and_orders_newest_first() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let linux_agent = store.resolve_agent("host-a", TelemetrySource::LinuxAgent).await.unwrap();
+        let dpi_agent = store.resolve_agent("probe-a", TelemetrySource::DpiProbe).await.unwrap();
+
+        let mut tx = store.begin().await.unwrap();
+        for minutes_ago in [30, 10, 20] {
+            tx.insert_raw(&raw(linux_agent, TelemetrySource::LinuxAgent, minutes_ago)).await.unwrap();
+        }
+        tx.insert_raw(&raw(dpi_agent, TelemetrySource::DpiProbe, 5)).await.unwrap();
+        tx.commit().await.unwrap();
+
+        let linux_only = store
+            .query(&TelemetryQuery { source: Some(TelemetrySource::LinuxAgent), limit: 100, ..Default::default() })
+            .await
+            .unwrap();
+        let ages: Vec<i64> = linux_only.iter().map(|e| e.payload_json["minutes_ago"].as_i64().unwrap()).collect();
+        assert_eq!(ages, vec![10, 20, 30]);
+
+        let recent = store
+            .query(&TelemetryQuery {
+                observed_from: Some(Utc::now() - Duration::minutes(15)),
+                limit: 100,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(recent.len(), 2);
+
+        let limited = store.query(&TelemetryQuery { limit: 1, ..Default::default() }).await.unwrap();
+        assert_eq!(limited.len(), 1);
+        assert_eq!(limited[0].source, TelemetrySource::DpiProbe);
+
+        assert!(store.query(&TelemetryQuery { limit: 0, ..Default::default() }).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_detection_insert_enforces_confidence_range() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let detection = DetectionRecord {
+            detection_engine: "ingest_rate_budget".to_string(),
+            detection_name: "ingest_rate_budget_exceeded".to_string(),
+            detection_category: Some("sensor_abuse".to_string()),
+            severity: "warning".to_string(),
+            confidence: 1.0,
+            reasoning: "test".to_string(),
+            artifacts: json!({ "component_id": "host-a" }),
+            deterministic_key: Sha256::digest(b"host-a").to_vec(),
+        };
+
+        let mut tx = store.begin().await.unwrap();
+        tx.insert_detection(&detection).await.unwrap();
+        assert!(tx.insert_detection(&DetectionRecord { confidence: 1.5, ..detection.clone() }).await.is_err());
+        tx.rollback().await;
+    }
+
+    #[tokio::test]
+    async fn test_identity_conflict_quarantine_and_resolution() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let agent_id = store.resolve_agent("host-a", TelemetrySource::LinuxAgent).await.unwrap();
+        let conflict = IdentityConflictRecord {
+            conflict_id: Uuid::new_v4(),
+            component_type: "linux_agent".to_string(),
+            component_identity: "host-a".to_string(),
+            agent_id,
+            first_origin: IdentityOrigin { source_ip: Some("10.0.0.1".parse().unwrap()), host_id: None },
+            conflicting_origin: IdentityOrigin { source_ip: Some("10.0.0.2".parse().unwrap()), host_id: None },
+            opened_at: Utc::now(),
+        };
+        let body = r#"{"envelope":{},"signature":"c2ln","signer_id":"k1","payload_hash":"00"}"#;
+        let quarantined = QuarantinedEventRecord {
+            conflict_id: conflict.conflict_id,
+            component_type: "linux_agent".to_string(),
+            component_identity: "host-a".to_string(),
+            message_id: Uuid::new_v4().to_string(),
+            origin: conflict.conflicting_origin.clone(),
+            signed_event: serde_json::value::RawValue::from_string(body.to_string()).unwrap(),
+            signed_event_sha256: Sha256::digest(body.as_bytes()).to_vec(),
+        };
+
+        let mut tx = store.begin().await.unwrap();
+        tx.insert_identity_conflict(&conflict, Uuid::new_v4()).await.unwrap();
+        tx.insert_quarantined_event(&quarantined).await.unwrap();
+        tx.insert_quarantined_event(&quarantined).await.unwrap();
+        tx.commit().await.unwrap();
+
+        let open = store.open_identity_conflicts().await.unwrap();
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].conflicting_origin, conflict.conflicting_origin);
+        // Quarantined events never reach raw_events
+        assert!(store.query(&all()).await.unwrap().is_empty());
+
+        let mut tx = store.begin().await.unwrap();
+        let released = tx.resolve_identity_conflict(conflict.conflict_id, ConflictResolution::Release, "fixed clone").await.unwrap();
+        assert_eq!(released, Some(2));
+        assert_eq!(tx.resolve_identity_conflict(conflict.conflict_id, ConflictResolution::Discard, "again").await.unwrap(), None);
+        tx.commit().await.unwrap();
+        assert!(store.open_identity_conflicts().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_standalone_audit_appends() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let first = storage::append_audit_standalone(&store, &audit("FIRST")).await.unwrap();
+        let second = storage::append_audit_standalone(&store, &audit("SECOND")).await.unwrap();
+        assert_ne!(first, second);
+    }
+
+    #[tokio::test]
+    async fn test_file_backed_store_persists_across_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lab").join("telemetry.sqlite3");
+
+        let agent_id = {
+            let store = SqliteStore::open(&path).unwrap();
+            let agent_id = store.resolve_agent("host-a", TelemetrySource::LinuxAgent).await.unwrap();
+            let mut tx = store.begin().await.unwrap();
+            tx.insert_raw(&raw(agent_id, TelemetrySource::LinuxAgent, 0)).await.unwrap();
+            tx.commit().await.unwrap();
+            agent_id
+        };
+
+        let store = SqliteStore::open(&path).unwrap();
+        assert_eq!(store.resolve_agent("host-a", TelemetrySource::LinuxAgent).await.unwrap(), agent_id);
+        assert_eq!(store.query(&all()).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_lab_file_without_payload_storage_is_upgraded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("telemetry.sqlite3");
+        {
+            // raw_events as created before payload_storage existed
+            let conn = rusqlite::Connection::open(&path).unwrap();
+            conn.execute_batch(
+                r#"
+                CREATE TABLE raw_events (
+                    raw_event_id TEXT PRIMARY KEY,
+                    source_type TEXT NOT NULL,
+                    source_agent_id TEXT NOT NULL,
+                    observed_at TEXT NOT NULL,
+                    received_at TEXT NOT NULL,
+                    event_name TEXT NOT NULL,
+                    payload_json TEXT NOT NULL,
+                    payload_sha256 BLOB NOT NULL
+                );
+                INSERT INTO raw_events VALUES ('6f1c1f4e-8f7e-4b6f-9a55-0d2a3c1b9e01', 'linux_agent',
+                    '6f1c1f4e-8f7e-4b6f-9a55-0d2a3c1b9e02', '2026-10-16T12:00:00.000000Z',
+                    '2026-10-16T12:00:00.000000Z', 'process', '{}', x'00');
+                "#,
+            )
+            .unwrap();
+        }
+
+        let store = SqliteStore::open(&path).unwrap();
+        let events = store.query(&all()).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].payload_storage, PayloadStorage::Full);
+    }
+}
@@ -0,0 +1,130 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/suppression_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for detection suppression rules - document validation, criteria matching and signer trust
+
+/*
+ * Detection Suppression Tests
+ *
+ * Rule documents are checked before activation (narrowing criteria, reason, bounded expiry,
+ * no unknown fields), criteria match detections exactly and by severity ceiling, and only
+ * signatures by configured signer keys are accepted.
+ */
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+    use crypto::signature::Ed25519KeyPair;
+    use serde_json::json;
+
+    use ingest::storage::DetectionRecord;
+    use ingest::suppression::{SuppressionRule, SuppressionSigners, MAX_LIFETIME_DAYS};
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 14, 12, 0, 0).unwrap()
+    }
+
+    fn document(criteria: serde_json::Value, expires_at: DateTime<Utc>) -> String {
+        json!({
+            "name": "backup-window-rate-budget",
+            "match": criteria,
+            "reason": "Nightly backup bursts (CHG-1234)",
+            "created_by": "soc-lead",
+            "expires_at": expires_at.to_rfc3339(),
+        })
+        .to_string()
+    }
+
+    fn detection(severity: &str, component_id: &str) -> DetectionRecord {
+        DetectionRecord {
+            detection_engine: "ingest_rate_budget".to_string(),
+            detection_name: "component_rate_budget_exceeded".to_string(),
+            detection_category: Some("ingest".to_string()),
+            severity: severity.to_string(),
+            confidence: 1.0,
+            reasoning: "Component exceeded its ingest budget".to_string(),
+            artifacts: json!({ "component_id": component_id, "events_per_second": 900 }),
+            deterministic_key: vec![0u8; 32],
+        }
+    }
+
+    #[test]
+    fn test_rule_documents_are_validated() {
+        let expires = now() + Duration::days(7);
+        let rule = SuppressionRule::parse(&document(json!({ "detection_engine": "ingest_rate_budget" }), expires), now())
+            .unwrap();
+        assert_eq!(rule.name, "backup-window-rate-budget");
+        assert_eq!(rule.expires_at, expires);
+
+        let refused = [
+            // Severity alone would silence whole classes of detections
+            document(json!({ "max_severity": "warning" }), expires),
+            document(json!({}), expires),
+            document(json!({ "detection_engine": "x", "max_severity": "severe" }), expires),
+            document(json!({ "detection_engine": "x", "artifacts": { "paths": ["/a"] } }), expires),
+            document(json!({ "detection_engine": "x", "host": "h" }), expires),
+            document(json!({ "detection_engine": " " }), expires),
+            document(json!({ "detection_engine": "x" }), now() - Duration::minutes(1)),
+            document(json!({ "detection_engine": "x" }), now() + Duration::days(MAX_LIFETIME_DAYS + 1)),
+        ];
+        for doc in refused {
+            assert!(SuppressionRule::parse(&doc, now()).is_err(), "accepted {}", doc);
+        }
+
+        let mut no_reason: serde_json::Value = serde_json::from_str(&document(json!({ "detection_engine": "x" }), expires)).unwrap();
+        no_reason["reason"] = json!("  ");
+        assert!(SuppressionRule::parse(&no_reason.to_string(), now()).is_err());
+    }
+
+    #[test]
+    fn test_criteria_matching() {
+        let expires = now() + Duration::days(7);
+        let rule = SuppressionRule::parse(
+            &document(
+                json!({
+                    "detection_engine": "ingest_rate_budget",
+                    "max_severity": "warning",
+                    "artifacts": { "component_id": "backup-01" },
+                }),
+                expires,
+            ),
+            now(),
+        )
+        .unwrap();
+
+        assert!(rule.criteria.matches(&detection("warning", "backup-01")));
+        assert!(rule.criteria.matches(&detection("info", "backup-01")));
+        assert!(!rule.criteria.matches(&detection("error", "backup-01")), "above max_severity");
+        assert!(!rule.criteria.matches(&detection("warning", "backup-02")), "artifact differs");
+        let mut other_engine = detection("warning", "backup-01");
+        other_engine.detection_engine = "lineage".to_string();
+        assert!(!rule.criteria.matches(&other_engine));
+
+        let by_category = SuppressionRule::parse(&document(json!({ "detection_category": "ingest" }), expires), now()).unwrap();
+        assert!(by_category.criteria.matches(&detection("critical", "any")));
+        let mut uncategorised = detection("info", "any");
+        uncategorised.detection_category = None;
+        assert!(!by_category.criteria.matches(&uncategorised));
+    }
+
+    #[test]
+    fn test_only_trusted_signatures_verify() {
+        let signer = Ed25519KeyPair::generate().unwrap();
+        let stranger = Ed25519KeyPair::generate().unwrap();
+        let signers = SuppressionSigners::parse(&format!(" {} ,", hex::encode(signer.public_key()))).unwrap();
+        assert!(!signers.is_empty());
+        assert!(SuppressionSigners::parse("").unwrap().is_empty());
+        assert!(SuppressionSigners::parse("not-hex").is_err());
+        assert!(SuppressionSigners::parse(&hex::encode([0u8; 16])).is_err());
+
+        let doc = document(json!({ "detection_engine": "ingest_rate_budget" }), now() + Duration::days(1));
+        let signature = signer.sign(doc.as_bytes());
+        assert!(signers.verify(&signer.public_key(), doc.as_bytes(), &signature).is_ok());
+
+        // Altered document, truncated signature, untrusted key
+        let altered = doc.replace("soc-lead", "intruder");
+        assert!(signers.verify(&signer.public_key(), altered.as_bytes(), &signature).is_err());
+        assert!(signers.verify(&signer.public_key(), doc.as_bytes(), &signature[..32]).is_err());
+        let foreign = stranger.sign(doc.as_bytes());
+        assert!(signers.verify(&stranger.public_key(), doc.as_bytes(), &foreign).is_err());
+    }
+}
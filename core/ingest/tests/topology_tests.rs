@@ -0,0 +1,184 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/topology_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for the signed site/zone topology - document validation, sensor and address placement, cross-zone lateral movement and the zone labels stored on raw_events
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+    use std::time::{Duration, Instant};
+
+    use chrono::Utc;
+    use crypto::signature::Ed25519KeyPair;
+    use serde_json::json;
+    use sha2::{Digest, Sha256};
+
+    use ingest::storage::{PayloadStorage, RawEventRecord, SqliteStore, TelemetryQuery, TelemetrySource, TelemetryStore};
+    use ingest::topology::{self, Endpoint, Topology, TopologySigners, TopologyState, ZoneLabel};
+
+    const DOCUMENT: &str = r#"
+version: 1
+sites:
+  - id: hq
+    name: Headquarters
+    zones:
+      - id: hq-office
+        cidrs: [10.10.0.0/16]
+        sensors: [ws-0142, ws-0143]
+      - id: hq-servers
+        cidrs: [10.20.0.0/16]
+        sensors: [dpi-hq-core]
+      - id: hq-admin
+        cidrs: [10.10.99.0/24]
+        sensors: [jump-01]
+  - id: plant
+    zones:
+      - id: plant-ot
+        cidrs: [172.16.40.0/22, "fd00:40::/64"]
+lateral_movement:
+  allowed:
+    - { from: hq-admin, to: hq-servers }
+"#;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn end(addr: &str, port: u16) -> Endpoint<'static> {
+        Endpoint { addr: Some(ip(addr)), port: Some(port), sensor_zone: None }
+    }
+
+    fn label(site: &str, zone: &str) -> ZoneLabel {
+        ZoneLabel { site: site.to_string(), zone: zone.to_string() }
+    }
+
+    #[test]
+    fn test_sensors_and_addresses_are_placed() {
+        let topology = Topology::parse(DOCUMENT).unwrap();
+        assert_eq!((topology.site_count(), topology.zone_count(), topology.sensor_count()), (2, 4, 4));
+        assert_eq!(topology.sensor_zone("ws-0142"), Some(&label("hq", "hq-office")));
+        assert_eq!(topology.sensor_zone("dpi-hq-core"), Some(&label("hq", "hq-servers")));
+        assert_eq!(topology.sensor_zone("unknown"), None);
+        // The most specific network wins: the admin /24 sits inside the office /16
+        assert_eq!(topology.address_zone(ip("10.10.99.7")), Some(&label("hq", "hq-admin")));
+        assert_eq!(topology.address_zone(ip("10.10.1.7")), Some(&label("hq", "hq-office")));
+        assert_eq!(topology.address_zone(ip("fd00:40::9")), Some(&label("plant", "plant-ot")));
+        assert_eq!(topology.address_zone(ip("192.0.2.1")), None);
+        assert_eq!(topology.document_sha256, hex::encode(Sha256::digest(DOCUMENT.as_bytes())));
+    }
+
+    #[test]
+    fn test_invalid_documents_rejected() {
+        let err = |doc: &str| Topology::parse(doc).unwrap_err();
+        let zones = |zones: &str| format!("version: 1\nsites:\n  - id: hq\n    zones:\n{}", zones);
+        assert!(err("version: 2\nsites: []").contains("not supported"));
+        assert!(err("version: 1\nsites: []").contains("no sites"));
+        assert!(err(&zones("      - { id: Office }")).contains("invalid zone id"));
+        assert!(err(&zones("      - { id: a }\n      - { id: a }")).contains("listed twice"));
+        assert!(err(&zones("      - { id: a, sensors: [s1] }\n      - { id: b, sensors: [s1] }")).contains("placed in zones"));
+        assert!(err(&zones("      - { id: a, cidrs: [10.0.0.0/8] }\n      - { id: b, cidrs: [10.0.0.0/8] }")).contains("is in zones"));
+        assert!(err(&zones("      - { id: a, cidrs: [10.0.0.1/8] }")).contains("host bits"));
+        assert!(err(&zones("      - { id: a, vlan: 12 }")).contains("unknown field"));
+        let route = |from: &str, to: &str| {
+            format!("{}\nlateral_movement:\n  allowed: [{{ from: {}, to: {} }}]", zones("      - { id: a }\n      - { id: b }"), from, to)
+        };
+        assert!(err(&route("a", "c")).contains("unknown zone 'c'"));
+        assert!(err(&route("a", "a")).contains("within one zone"));
+        assert!(Topology::parse(&route("a", "b")).is_ok());
+    }
+
+    #[test]
+    fn test_cross_zone_admin_connections_are_lateral_movement() {
+        let topology = Topology::parse(DOCUMENT).unwrap();
+        let office = label("hq", "hq-office");
+
+        // Agent in the office connects to SMB on a server: the agent's own end is its zone
+        let local = Endpoint { addr: Some(ip("10.10.1.7")), port: Some(50122), sensor_zone: Some(&office) };
+        let movement = topology.lateral_movement(&local, &end("10.20.0.5", 445)).unwrap();
+        assert_eq!((movement.source, movement.destination.clone()), (office.clone(), label("hq", "hq-servers")));
+        assert_eq!((movement.destination_addr, movement.port), (Some(ip("10.20.0.5")), 445));
+
+        // Inbound RDP to the office agent from the plant: the listening end is the destination
+        let rdp = Endpoint { addr: Some(ip("10.10.1.7")), port: Some(3389), sensor_zone: Some(&office) };
+        let inbound = topology.lateral_movement(&rdp, &end("172.16.41.3", 51000)).unwrap();
+        assert_eq!((inbound.source.zone.as_str(), inbound.destination.zone.as_str()), ("plant-ot", "hq-office"));
+
+        // Allowed route, same zone, ordinary ports and unplaced peers are not reported
+        assert!(topology.lateral_movement(&end("10.10.99.7", 50000), &end("10.20.0.5", 5985)).is_none());
+        assert!(topology.lateral_movement(&end("10.20.0.6", 50000), &end("10.20.0.5", 445)).is_none());
+        assert!(topology.lateral_movement(&local, &end("10.20.0.5", 443)).is_none());
+        assert!(topology.lateral_movement(&local, &end("203.0.113.9", 22)).is_none());
+        // The allowed route is directed
+        assert!(topology.lateral_movement(&end("10.20.0.5", 50000), &end("10.10.99.7", 22)).is_some());
+    }
+
+    #[test]
+    fn test_lateral_movement_reported_once_per_window() {
+        let state = TopologyState::new(Some(Topology::parse(DOCUMENT).unwrap()), Duration::from_secs(60));
+        let (src, dst) = (end("10.10.1.7", 50000), end("10.20.0.5", 445));
+        let t0 = Instant::now();
+        assert!(state.check_lateral(&src, &dst, t0).is_some());
+        assert!(state.check_lateral(&src, &dst, t0 + Duration::from_secs(30)).is_none());
+        // Another destination is its own movement
+        assert!(state.check_lateral(&src, &end("10.20.0.6", 445), t0 + Duration::from_secs(30)).is_some());
+        assert!(state.check_lateral(&src, &dst, t0 + Duration::from_secs(61)).is_some());
+
+        assert_eq!(state.label("ws-0143"), Some(label("hq", "hq-office")));
+        let disabled = TopologyState::disabled();
+        assert_eq!(disabled.label("ws-0143"), None);
+        assert!(disabled.check_lateral(&src, &dst, t0).is_none());
+    }
+
+    #[test]
+    fn test_document_loads_only_with_a_trusted_signature() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("topology.yaml");
+        let signer = Ed25519KeyPair::generate().unwrap();
+        let stranger = Ed25519KeyPair::generate().unwrap();
+        let signers = TopologySigners::parse(&hex::encode(signer.public_key())).unwrap();
+        let write = |document: &str, key: &Ed25519KeyPair, signed: &str| {
+            std::fs::write(&path, document).unwrap();
+            let sig = json!({ "signer_key": hex::encode(key.public_key()), "signature": hex::encode(key.sign(signed.as_bytes())) });
+            std::fs::write(dir.path().join("topology.yaml.sig"), sig.to_string()).unwrap();
+        };
+
+        write(DOCUMENT, &signer, DOCUMENT);
+        let topology = topology::load_signed(&path, &signers).unwrap();
+        assert_eq!(topology.zone_count(), 4);
+
+        write(DOCUMENT, &stranger, DOCUMENT);
+        assert!(topology::load_signed(&path, &signers).unwrap_err().contains("not a trusted topology signer"));
+
+        // A sensor moved after signing
+        write(&DOCUMENT.replace("jump-01", "ws-0142x"), &signer, DOCUMENT);
+        assert!(topology::load_signed(&path, &signers).unwrap_err().contains("does not verify"));
+
+        std::fs::remove_file(dir.path().join("topology.yaml.sig")).unwrap();
+        assert!(topology::load_signed(&path, &signers).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_zone_label_round_trips_through_raw_events() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let agent_id = store.resolve_agent("ws-0142", TelemetrySource::LinuxAgent).await.unwrap();
+        let payload = json!({ "event": "test" });
+        let record = RawEventRecord {
+            source: TelemetrySource::LinuxAgent,
+            agent_id,
+            observed_at: Utc::now(),
+            event_name: "network".to_string(),
+            payload_sha256: Sha256::digest(payload.to_string().as_bytes()).to_vec(),
+            payload_json: serde_json::value::to_raw_value(&payload).unwrap(),
+            payload_storage: PayloadStorage::Full,
+            payload_storage_reason: "policy:full".to_string(),
+            residency_region: None,
+            zone: Some(label("hq", "hq-office")),
+        };
+        let mut tx = store.begin().await.unwrap();
+        tx.insert_raw(&record).await.unwrap();
+        tx.commit().await.unwrap();
+
+        let events = store.query(&TelemetryQuery { limit: 10, ..Default::default() }).await.unwrap();
+        assert_eq!((events[0].site.as_deref(), events[0].zone.as_deref()), (Some("hq"), Some("hq-office")));
+        assert_eq!(serde_json::to_value(&events[0]).unwrap()["zone"], "hq-office");
+    }
+}
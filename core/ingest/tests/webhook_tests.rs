@@ -0,0 +1,66 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/webhook_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for lifecycle webhooks - signature round trip and tamper/skew rejection, subscription validation and the retry schedule
+
+/*
+ * Webhook Tests
+ *
+ * A receiver holding the secret accepts exactly the body that was signed, within the skew
+ * window. Subscriptions are HTTPS-only (loopback excepted) with known event types.
+ */
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use ingest::webhooks::{self, RetryPolicy};
+
+    const SECRET: &[u8] = b"0123456789abcdef0123456789abcdef";
+    const SKEW: Duration = Duration::from_secs(300);
+
+    fn types(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_signature_round_trip_and_rejections() {
+        let body = br#"{"event_type":"agent.enrolled"}"#;
+        let header = webhooks::sign(SECRET, 1_700_000_000, body);
+        assert!(header.starts_with("t=1700000000,v1="));
+        assert!(webhooks::verify(SECRET, &header, body, 1_700_000_100, SKEW));
+
+        // Tampered body, wrong secret, stale timestamp, malformed header
+        assert!(!webhooks::verify(SECRET, &header, br#"{"event_type":"x"}"#, 1_700_000_100, SKEW));
+        assert!(!webhooks::verify(b"another-secret-another-secret-xx", &header, body, 1_700_000_100, SKEW));
+        assert!(!webhooks::verify(SECRET, &header, body, 1_700_000_000 + 301, SKEW));
+        assert!(!webhooks::verify(SECRET, "v1=00", body, 1_700_000_000, SKEW));
+        // Re-stamping the timestamp without the secret breaks the signature
+        let restamped = header.replacen("t=1700000000", "t=1700000200", 1);
+        assert!(!webhooks::verify(SECRET, &restamped, body, 1_700_000_200, SKEW));
+    }
+
+    #[test]
+    fn test_subscription_validation() {
+        let all = types(&["*"]);
+        assert!(webhooks::validate_subscription("https://soc.example/hooks", &types(&["detection.created"])).is_ok());
+        assert!(webhooks::validate_subscription("http://127.0.0.1:9000/hook", &all).is_ok());
+        assert!(webhooks::validate_subscription("http://localhost/hook", &all).is_ok());
+        assert!(webhooks::validate_subscription("http://soc.example/hook", &all).is_err());
+        assert!(webhooks::validate_subscription("ftp://soc.example/hook", &all).is_err());
+        assert!(webhooks::validate_subscription("https://user:pw@soc.example/hook", &all).is_err());
+        assert!(webhooks::validate_subscription("not a url", &all).is_err());
+        assert!(webhooks::validate_subscription("https://soc.example/hook", &[]).is_err());
+        assert!(webhooks::validate_subscription("https://soc.example/hook", &types(&["agent.deleted"])).is_err());
+    }
+
+    #[test]
+    fn test_retry_schedule() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.next_delay(1), Some(Duration::from_secs(30)));
+        assert_eq!(policy.next_delay(2), Some(Duration::from_secs(60)));
+        assert_eq!(policy.next_delay(7), Some(Duration::from_secs(1920)));
+        assert_eq!(policy.next_delay(8), None);
+        let long = RetryPolicy { max_attempts: 40, ..policy };
+        assert_eq!(long.next_delay(39), Some(Duration::from_secs(3600)));
+    }
+}
@@ -0,0 +1,225 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/windows_mapping_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for the declarative Windows event log (channel + event ID) -> envelope.data mapping
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value as JsonValue};
+
+    use ingest::protocol::windows_mapping::{
+        WindowsEventMapping, WindowsEventMappings, WindowsEventRecord, UNMAPPED_CATEGORY,
+    };
+
+    const SYSMON: &str = "Microsoft-Windows-Sysmon/Operational";
+
+    fn mapping() -> WindowsEventMapping {
+        WindowsEventMappings::builtin().unwrap().for_version(None).unwrap().clone()
+    }
+
+    fn record(channel: &str, event_id: u32, event_data: JsonValue) -> WindowsEventRecord {
+        WindowsEventRecord {
+            channel: channel.to_string(),
+            event_id,
+            record_id: Some(88121),
+            computer: Some("WS-0142.corp.example".to_string()),
+            event_data: event_data.as_object().unwrap().clone(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_builtin_v1_covers_core_event_ids() {
+        let mappings = WindowsEventMappings::builtin().unwrap();
+        assert_eq!(mappings.versions(), vec![1]);
+        assert!(mappings.for_version(Some(2)).is_none());
+        let covered = mappings.for_version(Some(1)).unwrap().covered();
+        for (channel, event_id) in
+            [("Security", 4688), ("Security", 4624), ("Security", 4625), ("System", 7045), (SYSMON, 1), (SYSMON, 3), (SYSMON, 11)]
+        {
+            assert!(covered.contains(&(channel.to_string(), event_id)), "{} {} not mapped", channel, event_id);
+        }
+    }
+
+    #[test]
+    fn test_security_4688_maps_to_process_create() {
+        let mapped = mapping().map(&record(
+            "Security",
+            4688,
+            json!({
+                "SubjectUserName": "WS-0142$", "SubjectDomainName": "CORP", "SubjectLogonId": "0x3e7",
+                "NewProcessId": "0x1a2c", "NewProcessName": "C:\\Windows\\System32\\vssadmin.exe",
+                "ProcessId": "0x0f10", "ParentProcessName": "C:\\Windows\\System32\\cmd.exe",
+                "CommandLine": "vssadmin delete shadows /all /quiet",
+                "TargetUserName": "alice", "TargetDomainName": "CORP", "TargetLogonId": "0x8c1f2",
+                "TargetUserSid": "-", "TokenElevationType": "%%1937"
+            }),
+        ));
+        assert_eq!(mapped.rule.as_deref(), Some("security_process_create"));
+        assert_eq!((mapped.event_category.as_str(), mapped.event_type.as_str()), ("process", "Create"));
+        let data = &mapped.data;
+        assert_eq!(data["event_category"], "process");
+        // Security channel process IDs are hex
+        assert_eq!(data["pid"], 0x1a2c);
+        assert_eq!(data["process_data"]["event_type"], "Create");
+        assert_eq!(data["process_data"]["ppid"], 0x0f10);
+        assert_eq!(data["process_data"]["executable"], "C:\\Windows\\System32\\vssadmin.exe");
+        assert_eq!(data["process_data"]["command_line"], "vssadmin delete shadows /all /quiet");
+        assert_eq!(data["features"]["event_type"], "Create");
+        // Target account preferred over the subject; "-" counts as absent and falls through
+        assert_eq!(data["windows"]["user"], "alice");
+        assert_eq!(data["windows"]["logon_id"], "0x8c1f2");
+        assert_eq!(data["windows"]["user_sid"], JsonValue::Null);
+        assert_eq!(data["windows"]["channel"], "Security");
+        assert_eq!(data["windows"]["event_id"], 4688);
+        assert_eq!(data["windows"]["record_id"], 88121);
+        assert_eq!(data["windows"]["event_data"]["TokenElevationType"], "%%1937");
+        assert!(mapped.rejected.is_empty());
+    }
+
+    #[test]
+    fn test_logon_and_service_install_carry_windows_fields() {
+        let logon = mapping().map(&record(
+            "security",
+            4625,
+            json!({
+                "TargetUserName": "administrator", "TargetDomainName": "CORP", "LogonType": "3",
+                "IpAddress": "::ffff:192.0.2.44", "IpPort": "51712", "Status": "0xc000006d",
+                "SubStatus": "0xc000006a", "ProcessId": "0x0", "WorkstationName": "-"
+            }),
+        ));
+        // Channel names match case-insensitively
+        assert_eq!((logon.event_category.as_str(), logon.event_type.as_str()), ("authentication", "LogonFailed"));
+        assert_eq!(logon.data["windows"]["logon_type"], 3);
+        assert_eq!(logon.data["windows"]["source_addr"], "192.0.2.44");
+        assert_eq!(logon.data["windows"]["source_port"], 51712);
+        assert_eq!(logon.data["windows"]["sub_status"], "0xc000006a");
+        assert!(logon.data["windows"].get("workstation").is_none());
+        // No agent section for authentication events
+        assert!(logon.data.get("network_data").is_none());
+
+        let service = mapping().map(&record(
+            "System",
+            7045,
+            json!({
+                "ServiceName": "PSEXESVC", "ImagePath": "%SystemRoot%\\PSEXESVC.exe",
+                "ServiceType": "user mode service", "StartType": "demand start", "AccountName": "LocalSystem"
+            }),
+        ));
+        assert_eq!((service.event_category.as_str(), service.event_type.as_str()), ("service", "Install"));
+        assert_eq!(service.data["windows"]["service_name"], "PSEXESVC");
+        assert_eq!(service.data["windows"]["service_image"], "%SystemRoot%\\PSEXESVC.exe");
+        assert_eq!(service.data["features"]["event_type"], "Install");
+        assert!(service.data.get("pid").is_none());
+    }
+
+    #[test]
+    fn test_sysmon_process_network_and_file_events() {
+        let exec = mapping().map(&record(
+            SYSMON,
+            1,
+            json!({
+                "ProcessId": "6412", "ParentProcessId": "5120", "Image": "C:\\Users\\alice\\locker.exe",
+                "CommandLine": "locker.exe --encrypt C:\\", "ParentImage": "C:\\Windows\\explorer.exe",
+                "User": "CORP\\alice", "IntegrityLevel": "Medium",
+                "ProcessGuid": "{5e2c1d0a-8f31-6512-3a01-000000000d00}",
+                "Hashes": "SHA1=0F1E2D3C4B5A69788796A5B4C3D2E1F00F1E2D3C,SHA256=9F86D081884C7D659A2FEAA0C55AD015A3BF4F1B2B0B822CD15D6C15B0F00A08,IMPHASH=00000000000000000000000000000000"
+            }),
+        ));
+        assert_eq!(exec.data["pid"], 6412);
+        assert_eq!(exec.data["process_data"]["ppid"], 5120);
+        assert_eq!(
+            exec.data["process_data"]["sha256"],
+            "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"
+        );
+        assert_eq!(exec.data["windows"]["integrity_level"], "Medium");
+
+        let connect = mapping().map(&record(
+            SYSMON,
+            3,
+            json!({
+                "ProcessId": "6412", "Image": "C:\\Users\\alice\\locker.exe", "Protocol": "tcp", "Initiated": "true",
+                "SourceIp": "10.20.0.14", "SourcePort": "49822", "DestinationIp": "203.0.113.9",
+                "DestinationPort": "443", "DestinationHostname": "-"
+            }),
+        ));
+        assert_eq!((connect.event_category.as_str(), connect.event_type.as_str()), ("network", "Connect"));
+        let net = &connect.data["network_data"];
+        assert_eq!(net["event_type"], "Connect");
+        assert_eq!((net["local_addr"].as_str(), net["local_port"].as_u64()), (Some("10.20.0.14"), Some(49822)));
+        assert_eq!((net["remote_addr"].as_str(), net["remote_port"].as_u64()), (Some("203.0.113.9"), Some(443)));
+        assert_eq!(net["initiated"], true);
+        assert!(net.get("remote_hostname").is_none());
+
+        let created = mapping().map(&record(
+            SYSMON,
+            11,
+            json!({ "ProcessId": "6412", "TargetFilename": "C:\\Users\\alice\\Documents\\README_RESTORE.txt" }),
+        ));
+        assert_eq!(created.data["filesystem_data"]["event_type"], "Create");
+        assert_eq!(created.data["filesystem_data"]["path"], "C:\\Users\\alice\\Documents\\README_RESTORE.txt");
+
+        // Hashes without a SHA256 entry leave the field unset without rejecting it
+        let deleted = mapping().map(&record(
+            SYSMON,
+            23,
+            json!({ "ProcessId": "6412", "TargetFilename": "C:\\Users\\alice\\a.docx", "Hashes": "MD5=D41D8CD98F00B204E9800998ECF8427E", "IsExecutable": "false" }),
+        ));
+        assert_eq!(deleted.event_type, "Delete");
+        assert!(deleted.data["filesystem_data"].get("sha256").is_none());
+        assert_eq!(deleted.data["windows"]["is_executable"], false);
+        assert!(deleted.rejected.is_empty());
+    }
+
+    #[test]
+    fn test_bad_values_rejected_and_unmapped_records_pass_through() {
+        let mapped = mapping().map(&record(
+            SYSMON,
+            3,
+            json!({ "ProcessId": "not-a-pid", "SourceIp": "10.0.0.300", "DestinationPort": "70000", "Initiated": "yes" }),
+        ));
+        assert_eq!(
+            mapped.rejected,
+            vec!["pid", "network_data.initiated", "network_data.local_addr", "network_data.remote_port"]
+        );
+        assert!(mapped.data.get("pid").is_none());
+        // Raw EventData is kept as received
+        assert_eq!(mapped.data["windows"]["event_data"]["SourceIp"], "10.0.0.300");
+
+        let unmapped = mapping().map(&record("Security", 4740, json!({ "TargetUserName": "bob" })));
+        assert_eq!(unmapped.rule, None);
+        assert_eq!(unmapped.event_category, UNMAPPED_CATEGORY);
+        assert_eq!(unmapped.data["event_category"], UNMAPPED_CATEGORY);
+        assert_eq!(unmapped.data["windows"]["event_id"], 4740);
+        assert_eq!(unmapped.data["windows"]["event_data"]["TargetUserName"], "bob");
+    }
+
+    #[test]
+    fn test_invalid_mappings_rejected() {
+        let event = |fields: &str| {
+            format!(
+                r#"{{ "schema_version": 2, "events": [ {{ "name": "x", "channel": "Security", "event_id": 1,
+                   "event_category": "process", "event_type": "Create", "fields": [ {} ] }} ] }}"#,
+                fields
+            )
+        };
+        let err = |json: String| WindowsEventMapping::from_json(&json).unwrap_err();
+        assert!(err(event(r#"{ "target": "user.name", "sources": ["User"] }"#)).contains("unknown section"));
+        assert!(err(event(r#"{ "target": "windows.event_id", "sources": ["X"] }"#)).contains("set by the mapper"));
+        assert!(err(event(r#"{ "target": "process_data.event_type", "sources": ["X"] }"#)).contains("set by the mapper"));
+        assert!(err(event(r#"{ "target": "pid", "sources": ["ProcessId"], "coerce": "hex" }"#)).contains("unknown coerce"));
+        assert!(err(event(r#"{ "target": "pid", "sources": [] }"#)).contains("non-empty sources"));
+        assert!(err(event(
+            r#"{ "target": "pid", "sources": ["A"] }, { "target": "pid", "sources": ["B"] }"#
+        ))
+        .contains("mapped twice"));
+
+        let duplicate = r#"{ "schema_version": 2, "events": [
+            { "name": "a", "channel": "Security", "event_id": 4688, "event_category": "process", "event_type": "Create", "fields": [] },
+            { "name": "b", "channel": "SECURITY", "event_id": 4688, "event_category": "process", "event_type": "Create", "fields": [] } ] }"#;
+        assert!(WindowsEventMapping::from_json(duplicate).unwrap_err().contains("mapped twice"));
+        assert!(WindowsEventMapping::from_json(r#"{ "schema_version": 0, "events": [] }"#).is_err());
+
+        let ok = WindowsEventMapping::from_json(&event(r#"{ "target": "windows.task", "sources": ["TaskName"] }"#)).unwrap();
+        assert_eq!(ok.schema_version, 2);
+    }
+}
@@ -0,0 +1,104 @@
+// Path and File Name : /home/ransomeye/rebuild/core/ingest/tests/yara_rules_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for YARA rule distribution - rule pack document validation, signer trust, scan request validation and the agent poll answer
+
+/*
+ * YARA Rule Distribution Tests
+ *
+ * Rule pack documents need a name, a positive version, rules and a reason; only signatures by
+ * configured signer keys are accepted; scan requests name absolute, canonical paths; the poll
+ * answer leaves out what the agent does not need.
+ */
+
+#[cfg(test)]
+mod tests {
+    use crypto::signature::Ed25519KeyPair;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    use ingest::yara_rules::{ScanOrder, ScanRequest, YaraPollResponse, YaraRulePack, YaraSigners, MAX_SCAN_PATHS};
+
+    fn pack_doc() -> serde_json::Value {
+        json!({
+            "name": "ransomware-families",
+            "version": 42,
+            "rules": "rule LockBit_Note { strings: $a = \"Restore-My-Files.txt\" condition: $a }",
+            "reason": "Weekly threat intel refresh (CHG-2211)",
+            "created_by": "threat-intel",
+        })
+    }
+
+    fn scan(paths: &[&str]) -> ScanRequest {
+        ScanRequest {
+            agent_id: Uuid::new_v4(),
+            paths: paths.iter().map(|p| p.to_string()).collect(),
+            reason: "IR-1042".to_string(),
+            requested_by: "analyst".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_rule_pack_document_validation() {
+        let pack = YaraRulePack::parse(&pack_doc().to_string()).unwrap();
+        assert_eq!(pack.version, 42);
+        assert!(pack.rules.starts_with("rule LockBit_Note"));
+
+        for (field, value) in [
+            ("version", json!(0)),
+            ("rules", json!("")),
+            ("reason", json!("  ")),
+            ("name", json!("")),
+            ("created_by", json!("x".repeat(300))),
+        ] {
+            let mut doc = pack_doc();
+            doc[field] = value;
+            assert!(YaraRulePack::parse(&doc.to_string()).is_err(), "{} accepted", field);
+        }
+        let mut doc = pack_doc();
+        doc["expires_at"] = json!("2026-12-31T00:00:00Z");
+        assert!(YaraRulePack::parse(&doc.to_string()).is_err(), "unknown fields are refused");
+    }
+
+    #[test]
+    fn test_only_trusted_signatures_verify() {
+        let signer = Ed25519KeyPair::generate().unwrap();
+        let stranger = Ed25519KeyPair::generate().unwrap();
+        let signers = YaraSigners::parse(&format!("{}, ", hex::encode(signer.public_key()))).unwrap();
+        assert!(!signers.is_empty());
+        assert!(YaraSigners::parse("").unwrap().is_empty());
+        assert!(YaraSigners::parse("not-hex").is_err());
+
+        let doc = pack_doc().to_string();
+        let signature = signer.sign(doc.as_bytes());
+        assert!(signers.verify(&signer.public_key(), doc.as_bytes(), &signature).is_ok());
+        let altered = doc.replace("Restore-My-Files", "Restore-Your-Files");
+        assert!(signers.verify(&signer.public_key(), altered.as_bytes(), &signature).is_err());
+        let foreign = stranger.sign(doc.as_bytes());
+        assert!(signers.verify(&stranger.public_key(), doc.as_bytes(), &foreign).is_err());
+    }
+
+    #[test]
+    fn test_scan_request_paths() {
+        assert!(scan(&[]).validate().is_ok(), "no paths: the agent's own scan paths");
+        assert!(scan(&["/tmp", "/home/alice/Downloads"]).validate().is_ok());
+        assert!(scan(&["tmp"]).validate().is_err());
+        assert!(scan(&["/tmp/../etc"]).validate().is_err());
+        let many: Vec<String> = (0..=MAX_SCAN_PATHS).map(|i| format!("/srv/{}", i)).collect();
+        let refs: Vec<&str> = many.iter().map(String::as_str).collect();
+        assert!(scan(&refs).validate().is_err());
+
+        let mut anonymous = scan(&["/tmp"]);
+        anonymous.requested_by = " ".to_string();
+        assert!(anonymous.validate().is_err());
+    }
+
+    #[test]
+    fn test_poll_response_omits_what_is_current() {
+        assert_eq!(serde_json::to_value(YaraPollResponse::default()).unwrap(), json!({}));
+        let scan_id = Uuid::new_v4();
+        let answer = YaraPollResponse { pack: None, scan: Some(ScanOrder { scan_id, paths: vec![] }) };
+        let value = serde_json::to_value(&answer).unwrap();
+        assert!(value.get("pack").is_none());
+        assert_eq!(value["scan"]["scan_id"], scan_id.to_string());
+    }
+}
@@ -0,0 +1,249 @@
+// Path and File Name : /home/ransomeye/rebuild/core/kernel/src/crash.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Panic hook shared by core services - bounded local crash dump, best-effort service reporter (error_events) and a distinct exit code
+
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::fs;
+use std::panic::PanicHookInfo;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Exit code after a panic (EX_SOFTWARE). Fail-closed startup errors exit 1, so supervisors
+/// can tell a crash from a refusal to start.
+pub const PANIC_EXIT_CODE: i32 = 70;
+
+/// Crash dump directory unless RANSOMEYE_CRASH_DIR is set.
+pub const DEFAULT_CRASH_DIR: &str = "/var/lib/ransomeye/crash";
+
+/// Upper bound for one dump file; the backtrace is cut first.
+pub const MAX_DUMP_BYTES: usize = 64 * 1024;
+
+/// Dumps kept per service; older ones are removed when a new one is written.
+pub const MAX_DUMPS_PER_SERVICE: usize = 20;
+
+/// The reporter runs on its own thread and is abandoned after this long.
+const REPORTER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Best-effort delivery of a crash to the service's own store (e.g. an error_events row).
+pub type CrashReporter = Box<dyn Fn(&CrashReport) -> Result<(), String> + Send + Sync>;
+
+#[derive(Debug, Clone)]
+pub struct CrashReport {
+    pub service: String,
+    pub message: String,
+    /// file:line:column of the panic
+    pub location: Option<String>,
+    pub thread: String,
+    pub backtrace: String,
+    pub pid: u32,
+    pub occurred_at_unix_ms: u128,
+    /// Set once the dump file has been written
+    pub dump_path: Option<PathBuf>,
+}
+
+impl CrashReport {
+    /// Plain-text dump, at most `max_bytes`. Header lines are always kept; the backtrace is truncated.
+    pub fn render(&self, max_bytes: usize) -> String {
+        let mut out = format!(
+            "service: {}\npid: {}\nthread: {}\noccurred_at_unix_ms: {}\nlocation: {}\nmessage: {}\n\nbacktrace:\n",
+            self.service,
+            self.pid,
+            self.thread,
+            self.occurred_at_unix_ms,
+            self.location.as_deref().unwrap_or("unknown"),
+            self.message
+        );
+        out.push_str(&self.backtrace);
+        if out.len() > max_bytes {
+            const MARKER: &str = "\n[truncated]\n";
+            let keep = truncate_utf8(&out, max_bytes.saturating_sub(MARKER.len())).len();
+            out.truncate(keep);
+            out.push_str(MARKER);
+        }
+        out
+    }
+}
+
+/// Longest prefix of `s` not exceeding `max_bytes` that ends on a char boundary.
+pub fn truncate_utf8(s: &str, max_bytes: usize) -> &str {
+    if s.len() <= max_bytes {
+        return s;
+    }
+    let mut end = max_bytes;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+/// Panic payload as text (`panic!` with a literal yields &str, with formatting a String).
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        (*s).to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "non-string panic payload".to_string()
+    }
+}
+
+pub fn crash_dir() -> PathBuf {
+    std::env::var("RANSOMEYE_CRASH_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(DEFAULT_CRASH_DIR))
+}
+
+/// Write the dump and keep only the newest `keep` dumps of this service.
+pub fn write_dump(dir: &Path, report: &CrashReport, keep: usize) -> std::io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{}-{}-{}.crash", report.service, report.occurred_at_unix_ms, report.pid));
+    fs::write(&path, report.render(MAX_DUMP_BYTES))?;
+    prune_dumps(dir, &report.service, keep)?;
+    Ok(path)
+}
+
+fn prune_dumps(dir: &Path, service: &str, keep: usize) -> std::io::Result<()> {
+    let prefix = format!("{service}-");
+    let mut dumps: Vec<(SystemTime, PathBuf)> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .filter(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            name.starts_with(&prefix) && name.ends_with(".crash")
+        })
+        .filter_map(|e| Some((e.metadata().ok()?.modified().ok()?, e.path())))
+        .collect();
+    if dumps.len() <= keep {
+        return Ok(());
+    }
+    dumps.sort();
+    for (_, path) in &dumps[..dumps.len() - keep] {
+        let _ = fs::remove_file(path);
+    }
+    Ok(())
+}
+
+static IN_PANIC: AtomicBool = AtomicBool::new(false);
+
+/// Replace the default panic hook for `service`. Any panic, on any thread, writes a crash dump,
+/// hands the report to `reporter` (bounded by a timeout) and exits with PANIC_EXIT_CODE: a
+/// panicked task must not leave the service running half-dead.
+pub fn install(service: &str, reporter: Option<CrashReporter>) {
+    let service = service.to_string();
+    std::panic::set_hook(Box::new(move |info: &PanicHookInfo<'_>| {
+        // A panic inside the hook (or a second thread panicking meanwhile) must not recurse.
+        if IN_PANIC.swap(true, Ordering::SeqCst) {
+            std::process::exit(PANIC_EXIT_CODE);
+        }
+
+        let mut report = CrashReport {
+            service: service.clone(),
+            message: panic_message(info.payload()),
+            location: info.location().map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column())),
+            thread: std::thread::current().name().unwrap_or("unnamed").to_string(),
+            backtrace: Backtrace::force_capture().to_string(),
+            pid: std::process::id(),
+            occurred_at_unix_ms: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or(0),
+            dump_path: None,
+        };
+
+        eprintln!(
+            "PANIC in {} (thread '{}') at {}: {}",
+            report.service,
+            report.thread,
+            report.location.as_deref().unwrap_or("unknown"),
+            report.message
+        );
+
+        match write_dump(&crash_dir(), &report, MAX_DUMPS_PER_SERVICE) {
+            Ok(path) => {
+                eprintln!("Crash dump written to {}", path.display());
+                report.dump_path = Some(path);
+            }
+            Err(e) => eprintln!("Failed to write crash dump to {}: {}", crash_dir().display(), e),
+        }
+
+        if let Some(reporter) = &reporter {
+            // The reporter may block (DB connect); the panicking thread only waits so long.
+            let (tx, rx) = mpsc::channel();
+            std::thread::scope(|scope| {
+                let handle = scope.spawn(|| {
+                    let _ = tx.send(reporter(&report));
+                });
+                match rx.recv_timeout(REPORTER_TIMEOUT) {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => eprintln!("Crash reporter failed: {e}"),
+                    Err(_) => {
+                        eprintln!("Crash reporter timed out after {:?}", REPORTER_TIMEOUT);
+                        std::process::exit(PANIC_EXIT_CODE);
+                    }
+                }
+                let _ = handle.join();
+            });
+        }
+
+        std::process::exit(PANIC_EXIT_CODE);
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(backtrace: &str) -> CrashReport {
+        CrashReport {
+            service: "ransomeye-test".to_string(),
+            message: "index out of bounds".to_string(),
+            location: Some("src/lib.rs:10:5".to_string()),
+            thread: "tokio-runtime-worker".to_string(),
+            backtrace: backtrace.to_string(),
+            pid: 42,
+            occurred_at_unix_ms: 1_700_000_000_000,
+            dump_path: None,
+        }
+    }
+
+    #[test]
+    fn dump_is_bounded_and_keeps_header() {
+        let dump = report(&"é".repeat(100_000)).render(4096);
+        assert!(dump.len() <= 4096);
+        assert!(dump.starts_with("service: ransomeye-test\npid: 42\n"));
+        assert!(dump.contains("message: index out of bounds\n"));
+        assert!(dump.ends_with("[truncated]\n"));
+
+        let small = report("frame 0").render(4096);
+        assert!(small.ends_with("backtrace:\nframe 0"));
+    }
+
+    #[test]
+    fn truncation_respects_char_boundaries() {
+        assert_eq!(truncate_utf8("aé", 2), "a");
+        assert_eq!(truncate_utf8("abc", 10), "abc");
+        let payload: Box<dyn Any + Send> = Box::new(format!("bad {}", 1));
+        assert_eq!(panic_message(payload.as_ref()), "bad 1");
+        let payload: Box<dyn Any + Send> = Box::new(7u8);
+        assert_eq!(panic_message(payload.as_ref()), "non-string panic payload");
+    }
+
+    #[test]
+    fn old_dumps_are_pruned() {
+        let dir = std::env::temp_dir().join(format!("ransomeye-crash-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for i in 0..4u128 {
+            let mut r = report("frame");
+            r.occurred_at_unix_ms += i;
+            write_dump(&dir, &r, 2).unwrap();
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        fs::write(dir.join("other-service-1-1.crash"), "x").unwrap();
+        let names: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names.iter().filter(|n| n.starts_with("ransomeye-test-")).count(), 2);
+        assert!(names.iter().any(|n| n == "other-service-1-1.crash"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -2,6 +2,9 @@
 // Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
 // Details of functionality of this file: Core kernel - fail-closed trust initialization
 
+pub mod crash;
+pub mod otel;
+
 use std::path::Path;
 use std::fs;
 use thiserror::Error;
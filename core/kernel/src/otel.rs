@@ -0,0 +1,209 @@
+// Path and File Name : /home/ransomeye/rebuild/core/kernel/src/otel.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tracing subscriber setup with optional OTLP trace export (feature "otel") shared by the ingest and orchestrator binaries
+
+/*
+ * OpenTelemetry Trace Export
+ *
+ * Disabled unless RANSOMEYE_OTEL_ENABLED=true. Export is best-effort observability:
+ * an unreachable collector never blocks the service (spans are batched and dropped on overflow).
+ * Enabling export in a binary built without the "otel" feature is a configuration error
+ * and is reported, then tracing continues with the fmt layer only.
+ *
+ * Environment:
+ *   RANSOMEYE_OTEL_ENABLED        - true|false (default false)
+ *   OTEL_EXPORTER_OTLP_ENDPOINT   - OTLP/gRPC collector (default http://127.0.0.1:4317)
+ *   OTEL_SERVICE_NAME             - overrides the service name passed by the binary
+ *   RANSOMEYE_LOG_FILTER          - baseline log filter, e.g. "info" or "warn,ingest=debug" (default info)
+ *
+ * The log filter sits behind a reload layer so it can be changed at runtime (see `log_filter()`).
+ */
+
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::reload;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Registry;
+
+const DEFAULT_OTLP_ENDPOINT: &str = "http://127.0.0.1:4317";
+const DEFAULT_LOG_FILTER: &str = "info";
+
+/// Handle used to swap the active log filter at runtime.
+pub type LogFilterHandle = reload::Handle<Targets, Registry>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OtelConfig {
+    pub enabled: bool,
+    pub endpoint: String,
+    pub service_name: String,
+}
+
+impl OtelConfig {
+    pub fn from_env(default_service_name: &str) -> Self {
+        let enabled = std::env::var("RANSOMEYE_OTEL_ENABLED")
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+        let endpoint = std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| DEFAULT_OTLP_ENDPOINT.to_string());
+        let service_name = std::env::var("OTEL_SERVICE_NAME")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| default_service_name.to_string());
+        Self {
+            enabled,
+            endpoint,
+            service_name,
+        }
+    }
+}
+
+/// Flushes pending spans on drop. Hold it for the lifetime of `main`.
+pub struct OtelGuard {
+    active: bool,
+    log_filter: LogFilterHandle,
+    baseline_filter: String,
+}
+
+impl OtelGuard {
+    pub fn log_filter(&self) -> LogFilterHandle {
+        self.log_filter.clone()
+    }
+
+    /// Filter string in effect at startup (what runtime overrides revert to).
+    pub fn baseline_filter(&self) -> &str {
+        &self.baseline_filter
+    }
+}
+
+impl Drop for OtelGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otel")]
+        if self.active {
+            opentelemetry::global::shutdown_tracer_provider();
+        }
+        #[cfg(not(feature = "otel"))]
+        let _ = self.active;
+    }
+}
+
+/// RANSOMEYE_LOG_FILTER if it parses, otherwise the default filter and the reason it was not used.
+fn baseline_log_filter(value: Option<&str>) -> (String, Option<String>) {
+    match value {
+        Some(v) if !v.is_empty() => match v.parse::<Targets>() {
+            Ok(_) => (v.to_string(), None),
+            Err(e) => (DEFAULT_LOG_FILTER.to_string(), Some(format!("Invalid RANSOMEYE_LOG_FILTER '{}': {}", v, e))),
+        },
+        _ => (DEFAULT_LOG_FILTER.to_string(), None),
+    }
+}
+
+/// Install the global subscriber (fmt layer, plus OTLP layer when enabled). Call once from `main`.
+pub fn init_tracing(default_service_name: &str) -> OtelGuard {
+    let cfg = OtelConfig::from_env(default_service_name);
+    let (baseline_filter, filter_error) = baseline_log_filter(std::env::var("RANSOMEYE_LOG_FILTER").ok().as_deref());
+    let targets: Targets = baseline_filter.parse().unwrap_or_default();
+    let (filter_layer, log_filter) = reload::Layer::new(targets);
+    let fmt_layer = tracing_subscriber::fmt::layer();
+    let guard = |active: bool| OtelGuard {
+        active,
+        log_filter: log_filter.clone(),
+        baseline_filter: baseline_filter.clone(),
+    };
+    if let Some(e) = filter_error {
+        eprintln!("{}; falling back to '{}'", e, DEFAULT_LOG_FILTER);
+    }
+
+    if !cfg.enabled {
+        tracing_subscriber::registry().with(filter_layer).with(fmt_layer).init();
+        return guard(false);
+    }
+
+    #[cfg(feature = "otel")]
+    {
+        match build_tracer(&cfg) {
+            Ok(tracer) => {
+                tracing_subscriber::registry()
+                    .with(filter_layer)
+                    .with(fmt_layer)
+                    .with(tracing_opentelemetry::layer().with_tracer(tracer))
+                    .init();
+                tracing::info!(
+                    "OTLP trace export enabled | service={} | endpoint={}",
+                    cfg.service_name,
+                    cfg.endpoint
+                );
+                guard(true)
+            }
+            Err(e) => {
+                tracing_subscriber::registry().with(filter_layer).with(fmt_layer).init();
+                tracing::error!("OTLP trace export disabled: exporter init failed: {}", e);
+                guard(false)
+            }
+        }
+    }
+
+    #[cfg(not(feature = "otel"))]
+    {
+        tracing_subscriber::registry().with(filter_layer).with(fmt_layer).init();
+        tracing::error!(
+            "RANSOMEYE_OTEL_ENABLED is set but this binary was built without the \"otel\" feature; traces are not exported"
+        );
+        guard(false)
+    }
+}
+
+#[cfg(feature = "otel")]
+fn build_tracer(cfg: &OtelConfig) -> Result<opentelemetry_sdk::trace::Tracer, String> {
+    use opentelemetry::KeyValue;
+    use opentelemetry_otlp::WithExportConfig;
+    use opentelemetry_sdk::{trace, Resource};
+
+    opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(cfg.endpoint.clone()),
+        )
+        .with_trace_config(
+            trace::config().with_resource(Resource::new(vec![KeyValue::new(
+                "service.name",
+                cfg.service_name.clone(),
+            )])),
+        )
+        .install_batch(opentelemetry_sdk::runtime::Tokio)
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_log_filter_falls_back_to_default() {
+        assert_eq!(baseline_log_filter(Some("warn,ingest=debug")), ("warn,ingest=debug".to_string(), None));
+        assert_eq!(baseline_log_filter(None), (DEFAULT_LOG_FILTER.to_string(), None));
+        assert_eq!(baseline_log_filter(Some("")), (DEFAULT_LOG_FILTER.to_string(), None));
+
+        let (filter, error) = baseline_log_filter(Some("ingest=loud"));
+        assert_eq!(filter, DEFAULT_LOG_FILTER);
+        assert!(error.unwrap().contains("Invalid RANSOMEYE_LOG_FILTER 'ingest=loud'"));
+    }
+
+    #[test]
+    fn export_disabled_by_default() {
+        // The only test touching these variables or the global subscriber
+        std::env::remove_var("RANSOMEYE_OTEL_ENABLED");
+        std::env::remove_var("RANSOMEYE_LOG_FILTER");
+        let cfg = OtelConfig::from_env("ransomeye-test");
+        assert!(!cfg.enabled);
+
+        // Disabled: fmt layer only, nothing to flush, and the filter can still be swapped at runtime
+        let guard = init_tracing("ransomeye-test");
+        assert!(!guard.active);
+        assert_eq!(guard.baseline_filter(), DEFAULT_LOG_FILTER);
+        guard.log_filter().reload("warn".parse::<Targets>().unwrap()).unwrap();
+    }
+}
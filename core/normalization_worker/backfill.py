@@ -0,0 +1,408 @@
+# Path and File Name : /home/ransomeye/rebuild/core/normalization_worker/backfill.py
+# Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+# Details of functionality of this file: Normalization backfill - re-normalizes historical raw_events of a time range with the current normalizer into staged versioned rows, with resumable checkpoints, and cuts them over into normalized_events
+
+"""
+Normalization Backfill
+
+When the normalizer changes (NORMALIZER_VERSION in normalize.py is raised), rows it produced
+earlier keep the old mapping. This tool re-runs normalize_event() over a raw_events.received_at
+range:
+
+  run      Stages the new version of every event in the range whose normalized_events row has
+           an older normalizer_version into normalized_event_versions. Live rows are untouched,
+           so readers keep the old version until cutover.
+  cutover  Copies the staged fields into the live rows in place (normalized_event_id, source ids
+           and everything referencing the row stay as they are) and removes the staged rows.
+  status   Lists backfills with their progress.
+
+A backfill is identified by (NORMALIZER_VERSION, --from, --to). Each batch stages its rows and
+advances the checkpoint in one transaction, so rerunning the same command after a crash resumes
+after the last committed batch without staging or counting anything twice. Cutover is batched
+the same way and can be rerun until it reports cut_over.
+
+Raw events stored as a payload summary (payload_storage = 'summary') and events the normalizer
+rejects keep their old version; they are counted as skipped and failed.
+"""
+
+import argparse
+import hashlib
+import json
+import logging
+import sys
+import uuid
+from datetime import datetime, timezone
+
+from psycopg2.extras import RealDictCursor
+
+from normalize import (
+    NORMALIZER_VERSION,
+    get_db_connection,
+    get_or_create_normalization_component,
+    insert_immutable_audit_log,
+    normalize_event,
+)
+
+logging.basicConfig(
+    level=logging.INFO,
+    format='%(asctime)s [%(levelname)s] %(message)s'
+)
+logger = logging.getLogger(__name__)
+
+DEFAULT_BATCH_SIZE = 500
+MAX_BATCH_SIZE = 10000
+
+
+def parse_timestamp(value):
+    """RFC 3339 timestamp with an explicit offset (argparse type)."""
+    try:
+        ts = datetime.fromisoformat(value.replace('Z', '+00:00'))
+    except ValueError:
+        raise argparse.ArgumentTypeError(f"invalid RFC 3339 timestamp '{value}'")
+    if ts.tzinfo is None:
+        raise argparse.ArgumentTypeError(f"timestamp '{value}' has no UTC offset")
+    return ts.astimezone(timezone.utc)
+
+
+def parse_batch_size(value):
+    try:
+        n = int(value)
+    except ValueError:
+        raise argparse.ArgumentTypeError(f"invalid batch size '{value}'")
+    if not 1 <= n <= MAX_BATCH_SIZE:
+        raise argparse.ArgumentTypeError(f"batch size must be 1-{MAX_BATCH_SIZE}")
+    return n
+
+
+def find_or_create_backfill(conn, range_start, range_end):
+    """The backfill of this range and NORMALIZER_VERSION, created on first use."""
+    cursor = conn.cursor(cursor_factory=RealDictCursor)
+    try:
+        cursor.execute("""
+            INSERT INTO normalization_backfills (backfill_id, normalizer_version, range_start, range_end)
+            VALUES (%s, %s, %s, %s)
+            ON CONFLICT (normalizer_version, range_start, range_end) DO NOTHING
+        """, (str(uuid.uuid4()), NORMALIZER_VERSION, range_start, range_end))
+        conn.commit()
+        return load_backfill(conn, range_start, range_end)
+    finally:
+        cursor.close()
+
+
+def load_backfill(conn, range_start, range_end, for_update=False):
+    cursor = conn.cursor(cursor_factory=RealDictCursor)
+    try:
+        cursor.execute("""
+            SELECT * FROM normalization_backfills
+            WHERE normalizer_version = %s AND range_start = %s AND range_end = %s
+        """ + (" FOR UPDATE" if for_update else ""), (NORMALIZER_VERSION, range_start, range_end))
+        return cursor.fetchone()
+    finally:
+        cursor.close()
+
+
+def stage_batch(conn, range_start, range_end, batch_size):
+    """Stages one batch after the checkpoint. Returns the number of raw events read (0 = done)."""
+    cursor = conn.cursor(cursor_factory=RealDictCursor)
+    try:
+        # Row lock: a second run of the same backfill waits here and then continues after our checkpoint
+        backfill = load_backfill(conn, range_start, range_end, for_update=True)
+        if backfill['status'] != 'running':
+            conn.commit()
+            return 0
+
+        cursor.execute("""
+            SELECT re.raw_event_id, re.source_type::text AS source_type, re.source_agent_id,
+                   re.source_component_id, re.observed_at, re.received_at, re.payload_json, re.payload_storage
+            FROM raw_events re
+            WHERE re.received_at >= %(start)s AND re.received_at < %(end)s
+              AND (%(ckpt_at)s::timestamptz IS NULL OR (re.received_at, re.raw_event_id) > (%(ckpt_at)s, %(ckpt_id)s::uuid))
+              AND EXISTS (
+                  SELECT 1 FROM normalized_events ne
+                  WHERE ne.raw_event_id = re.raw_event_id AND ne.normalizer_version < %(version)s
+              )
+            ORDER BY re.received_at ASC, re.raw_event_id ASC
+            LIMIT %(limit)s
+        """, {
+            'start': range_start,
+            'end': range_end,
+            'ckpt_at': backfill['checkpoint_received_at'],
+            'ckpt_id': backfill['checkpoint_raw_event_id'],
+            'version': NORMALIZER_VERSION,
+            'limit': batch_size,
+        })
+        raw_events = cursor.fetchall()
+
+        staged = failed = skipped = 0
+        for raw_event in raw_events:
+            if raw_event['payload_storage'] == 'summary':
+                skipped += 1
+                continue
+            # A row the database rejects (e.g. an unknown severity) must not abort the batch
+            cursor.execute("SAVEPOINT backfill_row")
+            try:
+                normalized = normalize_event(raw_event)
+                cursor.execute("""
+                    INSERT INTO normalized_event_versions (
+                        raw_event_id, normalizer_version, backfill_id, observed_at, source_type,
+                        event_kind, event_subkind, severity, attributes, deterministic_key
+                    )
+                    VALUES (%s, %s, %s, %s, %s, %s, %s, %s, %s, %s)
+                    ON CONFLICT (raw_event_id, normalizer_version) DO NOTHING
+                """, (
+                    normalized['raw_event_id'],
+                    NORMALIZER_VERSION,
+                    backfill['backfill_id'],
+                    normalized['observed_at'],
+                    normalized['source_type'],
+                    normalized['event_kind'],
+                    normalized['event_subkind'],
+                    normalized['severity'],
+                    json.dumps(normalized['attributes']) if normalized['attributes'] else None,
+                    normalized['deterministic_key'],
+                ))
+                staged += cursor.rowcount
+                cursor.execute("RELEASE SAVEPOINT backfill_row")
+            except Exception as e:
+                cursor.execute("ROLLBACK TO SAVEPOINT backfill_row")
+                failed += 1
+                logger.error(f"Backfill cannot re-normalize raw_event_id={raw_event['raw_event_id']}: {e}")
+
+        if raw_events:
+            last = raw_events[-1]
+            cursor.execute("""
+                UPDATE normalization_backfills
+                SET checkpoint_received_at = %s, checkpoint_raw_event_id = %s,
+                    events_read = events_read + %s, events_staged = events_staged + %s,
+                    events_failed = events_failed + %s, events_skipped = events_skipped + %s,
+                    updated_at = now()
+                WHERE backfill_id = %s
+            """, (last['received_at'], last['raw_event_id'], len(raw_events), staged, failed, skipped,
+                  backfill['backfill_id']))
+        else:
+            cursor.execute("""
+                UPDATE normalization_backfills
+                SET status = 'staged', staged_at = now(), updated_at = now()
+                WHERE backfill_id = %s
+            """, (backfill['backfill_id'],))
+        conn.commit()
+        return len(raw_events)
+    except Exception:
+        conn.rollback()
+        raise
+    finally:
+        cursor.close()
+
+
+def cutover_batch(conn, backfill_id, batch_size):
+    """Swaps one batch of staged rows into normalized_events. Returns the staged rows consumed."""
+    cursor = conn.cursor()
+    try:
+        cursor.execute("""
+            WITH batch AS (
+                SELECT raw_event_id FROM normalized_event_versions
+                WHERE backfill_id = %(id)s
+                LIMIT %(limit)s
+                FOR UPDATE
+            ),
+            swapped AS (
+                UPDATE normalized_events n
+                SET normalized_at = v.normalized_at, observed_at = v.observed_at, source_type = v.source_type,
+                    event_kind = v.event_kind, event_subkind = v.event_subkind, severity = v.severity,
+                    attributes = v.attributes, deterministic_key = v.deterministic_key,
+                    normalizer_version = v.normalizer_version
+                FROM normalized_event_versions v
+                JOIN batch b ON b.raw_event_id = v.raw_event_id
+                WHERE v.backfill_id = %(id)s
+                  AND n.raw_event_id = v.raw_event_id
+                  AND n.normalizer_version < v.normalizer_version
+                RETURNING n.normalized_event_id
+            ),
+            removed AS (
+                DELETE FROM normalized_event_versions v
+                USING batch b
+                WHERE v.backfill_id = %(id)s AND v.raw_event_id = b.raw_event_id
+                RETURNING v.raw_event_id
+            )
+            SELECT (SELECT count(*) FROM swapped), (SELECT count(*) FROM removed)
+        """, {'id': backfill_id, 'limit': batch_size})
+        swapped, removed = cursor.fetchone()
+        cursor.execute("""
+            UPDATE normalization_backfills
+            SET events_cut_over = events_cut_over + %s, updated_at = now()
+            WHERE backfill_id = %s
+        """, (swapped, backfill_id))
+        conn.commit()
+        return removed
+    except Exception:
+        conn.rollback()
+        raise
+    finally:
+        cursor.close()
+
+
+def finish_cutover(conn, backfill_id):
+    """Marks the backfill cut over and audits it in the same transaction."""
+    cursor = conn.cursor(cursor_factory=RealDictCursor)
+    try:
+        component_id = get_or_create_normalization_component(conn)
+        cursor.execute("""
+            UPDATE normalization_backfills
+            SET status = 'cut_over', cut_over_at = now(), updated_at = now()
+            WHERE backfill_id = %s
+            RETURNING *
+        """, (backfill_id,))
+        backfill = cursor.fetchone()
+        payload = {
+            "backfill_id": str(backfill['backfill_id']),
+            "normalizer_version": backfill['normalizer_version'],
+            "range_start": backfill['range_start'].isoformat(),
+            "range_end": backfill['range_end'].isoformat(),
+            "events_read": backfill['events_read'],
+            "events_staged": backfill['events_staged'],
+            "events_failed": backfill['events_failed'],
+            "events_skipped": backfill['events_skipped'],
+            "events_cut_over": backfill['events_cut_over'],
+        }
+        payload_sha256 = hashlib.sha256(json.dumps(payload, sort_keys=True).encode()).digest()
+        insert_immutable_audit_log(
+            conn,
+            component_id,
+            None,
+            "NORMALIZATION_BACKFILL_CUTOVER",
+            "other",
+            backfill['backfill_id'],
+            backfill['cut_over_at'],
+            payload,
+            payload_sha256,
+        )
+        conn.commit()
+        return backfill
+    except Exception:
+        conn.rollback()
+        raise
+    finally:
+        cursor.close()
+
+
+def describe(backfill):
+    checkpoint = backfill['checkpoint_received_at'].isoformat() if backfill['checkpoint_received_at'] else '-'
+    return (
+        f"backfill_id={backfill['backfill_id']} version={backfill['normalizer_version']} "
+        f"range=[{backfill['range_start'].isoformat()}, {backfill['range_end'].isoformat()}) "
+        f"status={backfill['status']} read={backfill['events_read']} staged={backfill['events_staged']} "
+        f"failed={backfill['events_failed']} skipped={backfill['events_skipped']} "
+        f"cut_over={backfill['events_cut_over']} checkpoint={checkpoint}"
+    )
+
+
+def cmd_run(conn, args):
+    backfill = find_or_create_backfill(conn, args.range_start, args.range_end)
+    if backfill['status'] != 'running':
+        logger.info(f"Nothing to stage: {describe(backfill)}")
+        return 0
+    if backfill['checkpoint_received_at']:
+        logger.info(f"Resuming after checkpoint: {describe(backfill)}")
+    else:
+        logger.info(f"Starting: {describe(backfill)}")
+
+    while stage_batch(conn, args.range_start, args.range_end, args.batch_size) > 0:
+        logger.info(f"Progress: {describe(load_backfill(conn, args.range_start, args.range_end))}")
+    conn.commit()
+
+    backfill = load_backfill(conn, args.range_start, args.range_end)
+    conn.commit()
+    logger.info(f"Staged: {describe(backfill)}")
+    if backfill['events_failed'] or backfill['events_skipped']:
+        logger.warning(
+            f"{backfill['events_failed']} failed and {backfill['events_skipped']} skipped event(s) keep "
+            f"their old version after cutover"
+        )
+    return 0
+
+
+def cmd_cutover(conn, args):
+    backfill = load_backfill(conn, args.range_start, args.range_end)
+    conn.commit()
+    if backfill is None:
+        logger.error("FAIL-CLOSED: No backfill of this range and normalizer version; stage it with 'run' first")
+        return 1
+    if backfill['status'] == 'running':
+        logger.error(f"FAIL-CLOSED: Staging has not finished; rerun 'run' first: {describe(backfill)}")
+        return 1
+    if backfill['status'] == 'cut_over':
+        logger.info(f"Already cut over: {describe(backfill)}")
+        return 0
+
+    cursor = conn.cursor()
+    cursor.execute("""
+        UPDATE normalization_backfills SET status = 'cutting_over', updated_at = now()
+        WHERE backfill_id = %s AND status = 'staged'
+    """, (backfill['backfill_id'],))
+    conn.commit()
+    cursor.close()
+
+    while cutover_batch(conn, backfill['backfill_id'], args.batch_size) > 0:
+        logger.info(f"Progress: {describe(load_backfill(conn, args.range_start, args.range_end))}")
+    conn.commit()
+    backfill = finish_cutover(conn, backfill['backfill_id'])
+    logger.info(f"Cut over: {describe(backfill)}")
+    return 0
+
+
+def cmd_status(conn, args):
+    cursor = conn.cursor(cursor_factory=RealDictCursor)
+    cursor.execute("SELECT * FROM normalization_backfills ORDER BY started_at ASC")
+    for backfill in cursor.fetchall():
+        print(describe(backfill))
+    cursor.close()
+    conn.commit()
+    print(f"Current NORMALIZER_VERSION={NORMALIZER_VERSION}")
+    return 0
+
+
+def main():
+    parser = argparse.ArgumentParser(
+        description="Re-normalize historical raw_events with the current normalizer "
+                    f"(NORMALIZER_VERSION={NORMALIZER_VERSION})"
+    )
+    sub = parser.add_subparsers(dest='command', required=True)
+    for name, help_text in (
+        ('run', 'stage re-normalized rows for a received_at range (resumes from its checkpoint)'),
+        ('cutover', 'replace the live rows of a staged range with their new version'),
+    ):
+        p = sub.add_parser(name, help=help_text)
+        p.add_argument('--from', dest='range_start', type=parse_timestamp, required=True,
+                       help='start of the raw_events.received_at range (RFC 3339, inclusive)')
+        p.add_argument('--to', dest='range_end', type=parse_timestamp, required=True,
+                       help='end of the range (RFC 3339, exclusive)')
+        p.add_argument('--batch-size', type=parse_batch_size, default=DEFAULT_BATCH_SIZE,
+                       help=f'raw events per checkpointed transaction (default {DEFAULT_BATCH_SIZE})')
+    sub.add_parser('status', help='list backfills and their progress')
+    args = parser.parse_args()
+
+    if args.command in ('run', 'cutover') and args.range_start >= args.range_end:
+        parser.error('--from must be earlier than --to')
+
+    try:
+        conn = get_db_connection()
+        cursor = conn.cursor()
+        cursor.execute("SET search_path = ransomeye, public;")
+        conn.commit()
+        cursor.close()
+    except Exception as e:
+        logger.error(f"FAIL-CLOSED: Database connection failed: {e}")
+        return 1
+
+    commands = {'run': cmd_run, 'cutover': cmd_cutover, 'status': cmd_status}
+    try:
+        return commands[args.command](conn, args)
+    except Exception as e:
+        logger.error(f"FAIL-CLOSED: Backfill {args.command} failed: {e}")
+        return 1
+    finally:
+        conn.close()
+
+
+if __name__ == '__main__':
+    sys.exit(main())
@@ -28,6 +28,14 @@ logging.basicConfig(
 )
 logger = logging.getLogger(__name__)
 
+# Advisory lock key serializing immutable_audit_log chain extension across all writers
+# (must match AUDIT_CHAIN_LOCK_KEY in core/ingest/src/storage/postgres.rs). ASCII "REAUDIT".
+AUDIT_CHAIN_LOCK_KEY = 0x52454155444954
+
+# Stamped on every normalized_events row. Raise it whenever the mapping below changes, then
+# re-normalize history with backfill.py (older rows stay live until the backfill is cut over).
+NORMALIZER_VERSION = 1
+
 def get_db_connection():
     """Get database connection from environment variables."""
     db_host = os.environ.get('DB_HOST', 'localhost')
@@ -92,11 +100,15 @@ def insert_immutable_audit_log(conn, actor_component_id, actor_agent_id, action,
     """Insert into immutable_audit_log (fail-closed)."""
     cursor = conn.cursor()
     try:
+        # Serialize chain extension across all writers (held until the caller commits;
+        # connection runs with autocommit=False so this is transaction-scoped)
+        cursor.execute("SELECT pg_advisory_xact_lock(%s)", (AUDIT_CHAIN_LOCK_KEY,))
+
         # Get previous audit chain entry for hash chaining
         cursor.execute("""
             SELECT audit_id, chain_hash_sha256, payload_sha256
             FROM ransomeye.immutable_audit_log
-            ORDER BY created_at DESC
+            ORDER BY created_at DESC, audit_id DESC
             LIMIT 1
         """)
         
@@ -119,10 +131,10 @@ def insert_immutable_audit_log(conn, actor_component_id, actor_agent_id, action,
         audit_id = uuid.uuid4()
         cursor.execute("""
             INSERT INTO ransomeye.immutable_audit_log (
-                audit_id, actor_component_id, actor_agent_id, action, object_type, object_id, event_time,
+                audit_id, created_at, actor_component_id, actor_agent_id, action, object_type, object_id, event_time,
                 payload_json, payload_sha256, prev_audit_id, prev_payload_sha256, chain_hash_sha256, signature_status
             )
-            VALUES (%s, %s, %s, %s, %s::text::trust_object_type, %s, %s, %s, %s, %s, %s, %s, 'unknown')
+            VALUES (%s, clock_timestamp(), %s, %s, %s, %s::text::trust_object_type, %s, %s, %s, %s, %s, %s, %s, 'unknown')
             RETURNING audit_id
         """, (
             audit_id,
@@ -264,9 +276,9 @@ def process_batch(conn, batch_size=100):
                 cursor.execute("""
                     INSERT INTO ransomeye.normalized_events (
                         raw_event_id, observed_at, source_type, source_agent_id, source_component_id,
-                        event_kind, event_subkind, severity, attributes, deterministic_key
+                        event_kind, event_subkind, severity, attributes, deterministic_key, normalizer_version
                     )
-                    VALUES (%s, %s, %s, %s, %s, %s, %s, %s, %s, %s)
+                    VALUES (%s, %s, %s, %s, %s, %s, %s, %s, %s, %s, %s)
                     RETURNING normalized_event_id
                 """, (
                     normalized['raw_event_id'],
@@ -279,6 +291,7 @@ def process_batch(conn, batch_size=100):
                     normalized['severity'],
                     json.dumps(normalized['attributes']) if normalized['attributes'] else None,
                     normalized['deterministic_key'],
+                    NORMALIZER_VERSION,
                 ))
                 
                 normalized_event_id = cursor.fetchone()[0]
@@ -0,0 +1,158 @@
+# Path and File Name : /home/ransomeye/rebuild/core/normalization_worker/tests/test_backfill.py
+# Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+# Details of functionality of this file: Tests for the normalization backfill - resuming a run from its checkpoint and rerunning over a range that was already processed
+
+"""
+Normalization Backfill Tests
+
+Runs backfill.py against a disposable database with the RansomEye schema applied:
+
+    RANSOMEYE_TEST_BACKFILL_DB=1 DB_HOST=... DB_PORT=... DB_NAME=... DB_USER=... DB_PASS=... \
+        python3 -m unittest discover -s core/normalization_worker/tests
+
+Without RANSOMEYE_TEST_BACKFILL_DB=1 (or without psycopg2) the tests are skipped. Every test
+works on its own received_at range, so runs do not see each other's rows. Live rows are written
+with normalizer_version 1 and the backfill runs as version 2.
+"""
+
+import argparse
+import hashlib
+import json
+import os
+import sys
+import unittest
+import uuid
+from datetime import datetime, timedelta, timezone
+from pathlib import Path
+from unittest import mock
+
+sys.path.insert(0, str(Path(__file__).parent.parent))
+
+ENABLED = os.environ.get('RANSOMEYE_TEST_BACKFILL_DB') == '1'
+
+try:
+    import backfill
+    import normalize
+except ImportError:
+    backfill = normalize = None
+
+BACKFILL_VERSION = 2
+
+
+@unittest.skipUnless(ENABLED and backfill is not None, "RANSOMEYE_TEST_BACKFILL_DB != 1 or psycopg2 missing")
+class TestNormalizationBackfill(unittest.TestCase):
+    """Checkpointed staging and cutover against a real database."""
+
+    def setUp(self):
+        self.conn = normalize.get_db_connection()
+        cursor = self.conn.cursor()
+        cursor.execute("SET search_path = ransomeye, public;")
+        self.conn.commit()
+        cursor.close()
+        # A range of its own, somewhere in 2001
+        self.range_start = datetime(2001, 1, 1, tzinfo=timezone.utc) + timedelta(seconds=uuid.uuid4().int % 31_000_000)
+        self.range_end = self.range_start + timedelta(minutes=1)
+        version = mock.patch.object(backfill, 'NORMALIZER_VERSION', BACKFILL_VERSION)
+        version.start()
+        self.addCleanup(version.stop)
+
+    def tearDown(self):
+        self.conn.close()
+
+    def add_events(self, count):
+        """Raw events one second apart in the range, each with a version 1 live row."""
+        cursor = self.conn.cursor()
+        ids = []
+        for i in range(count):
+            raw_event_id = str(uuid.uuid4())
+            payload = {"data": {"event_category": "process_exec", "pid": i}}
+            cursor.execute("""
+                INSERT INTO raw_events (raw_event_id, source_type, received_at, payload_json, payload_sha256)
+                VALUES (%s, 'linux_agent', %s, %s, %s)
+            """, (raw_event_id, self.range_start + timedelta(seconds=i), json.dumps(payload),
+                  hashlib.sha256(raw_event_id.encode()).digest()))
+            cursor.execute("""
+                INSERT INTO normalized_events (raw_event_id, source_type, event_kind, deterministic_key, normalizer_version)
+                VALUES (%s, 'linux_agent', 'old_kind', %s, 1)
+            """, (raw_event_id, hashlib.sha256(raw_event_id.encode()).digest()))
+            ids.append(raw_event_id)
+        self.conn.commit()
+        cursor.close()
+        return ids
+
+    def args(self, range_end=None, batch_size=2):
+        return argparse.Namespace(range_start=self.range_start, range_end=range_end or self.range_end,
+                                  batch_size=batch_size)
+
+    def backfill(self, range_end=None):
+        row = backfill.load_backfill(self.conn, self.range_start, range_end or self.range_end)
+        self.conn.commit()
+        return row
+
+    def query(self, sql, params):
+        cursor = self.conn.cursor()
+        cursor.execute(sql, params)
+        rows = cursor.fetchall()
+        self.conn.commit()
+        cursor.close()
+        return rows
+
+    def staged_rows(self, backfill_id):
+        return self.query("SELECT count(*) FROM normalized_event_versions WHERE backfill_id = %s",
+                          (str(backfill_id),))[0][0]
+
+    def live_versions(self, ids):
+        rows = self.query("SELECT normalizer_version, event_kind FROM normalized_events WHERE raw_event_id::text = ANY(%s)",
+                          (ids,))
+        return sorted(set((version, kind) for version, kind in rows))
+
+    def test_run_resumes_after_checkpoint(self):
+        ids = self.add_events(5)
+        backfill.find_or_create_backfill(self.conn, self.range_start, self.range_end)
+
+        # One committed batch, then the run stops (crash)
+        self.assertEqual(backfill.stage_batch(self.conn, self.range_start, self.range_end, 2), 2)
+        interrupted = self.backfill()
+        self.assertEqual(interrupted['status'], 'running')
+        self.assertEqual(str(interrupted['checkpoint_raw_event_id']), ids[1])
+        self.assertEqual(interrupted['events_read'], 2)
+
+        # Rerunning the command continues after the checkpoint: nothing staged or counted twice
+        self.assertEqual(backfill.cmd_run(self.conn, self.args()), 0)
+        done = self.backfill()
+        self.assertEqual(done['backfill_id'], interrupted['backfill_id'])
+        self.assertEqual(done['status'], 'staged')
+        self.assertEqual(str(done['checkpoint_raw_event_id']), ids[-1])
+        self.assertEqual((done['events_read'], done['events_staged'], done['events_failed']), (5, 5, 0))
+        self.assertEqual(self.staged_rows(done['backfill_id']), 5)
+        # Staging leaves the live rows alone
+        self.assertEqual(self.live_versions(ids), [(1, 'old_kind')])
+
+    def test_rerun_over_processed_range_changes_nothing(self):
+        ids = self.add_events(3)
+        self.assertEqual(backfill.cmd_run(self.conn, self.args()), 0)
+        self.assertEqual(backfill.cmd_cutover(self.conn, self.args()), 0)
+        cut_over = self.backfill()
+        self.assertEqual(cut_over['status'], 'cut_over')
+        self.assertEqual(cut_over['events_cut_over'], 3)
+        self.assertEqual(self.live_versions(ids), [(BACKFILL_VERSION, 'process_exec')])
+
+        # Same range again: the backfill is finished, run and cutover are no-ops
+        self.assertEqual(backfill.cmd_run(self.conn, self.args()), 0)
+        self.assertEqual(backfill.cmd_cutover(self.conn, self.args()), 0)
+        again = self.backfill()
+        self.assertEqual(again['cut_over_at'], cut_over['cut_over_at'])
+        self.assertEqual((again['events_read'], again['events_cut_over']), (3, 3))
+
+        # An overlapping range is a new backfill, but every live row is already at this version
+        wider = self.range_end + timedelta(seconds=1)
+        self.assertEqual(backfill.cmd_run(self.conn, self.args(range_end=wider)), 0)
+        overlap = self.backfill(range_end=wider)
+        self.assertNotEqual(overlap['backfill_id'], cut_over['backfill_id'])
+        self.assertEqual(overlap['status'], 'staged')
+        self.assertEqual((overlap['events_read'], overlap['events_staged']), (0, 0))
+        self.assertEqual(self.staged_rows(overlap['backfill_id']), 0)
+
+
+if __name__ == '__main__':
+    unittest.main()
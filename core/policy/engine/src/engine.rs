@@ -15,6 +15,7 @@ use crate::policy::PolicyLoader;
 use crate::evaluator::PolicyEvaluator;
 use crate::compiler::PolicyCompiler;
 use crate::audit::{initialize_audit_logger, log_decision};
+use crate::quota::{resolve_ingest_quotas, IngestQuota};
 
 #[path = "../../security/revocation.rs"]
 mod revocation;
@@ -27,6 +28,7 @@ pub struct PolicyEngine {
     started: Arc<AtomicBool>,
     engine_version: String,
     audit_enabled: bool,
+    ingest_quotas: Vec<IngestQuota>,
 }
 
 impl PolicyEngine {
@@ -43,7 +45,7 @@ impl PolicyEngine {
         let compiler = Arc::new(PolicyCompiler::new());
 
         let policies = policy_loader.get_all_policies();
-        for policy in policies {
+        for policy in &policies {
             if policy.signature.is_none() {
                 error!("Unsigned policy found: {}", policy.id);
                 return Err(PolicyError::EngineRefusedToStart(
@@ -57,6 +59,9 @@ impl PolicyEngine {
             }
         }
 
+        // Ingestion budgets are part of the signed policy set; bad quotas refuse startup like any bad policy
+        let ingest_quotas = resolve_ingest_quotas(&policies)?;
+
         let revocation_checker = Arc::new(PolicyRevocationChecker::new(
             revocation_list_path.unwrap_or("/etc/ransomeye/policy/revocation.list")
         )?);
@@ -79,6 +84,7 @@ impl PolicyEngine {
             started: Arc::new(AtomicBool::new(true)),
             engine_version: engine_version.to_string(),
             audit_enabled,
+            ingest_quotas,
         })
     }
 
@@ -161,5 +167,10 @@ impl PolicyEngine {
     pub fn version(&self) -> &str {
         &self.engine_version
     }
+
+    /// Effective per-component-type ingestion quotas, sorted by component type
+    pub fn ingest_quotas(&self) -> &[IngestQuota] {
+        &self.ingest_quotas
+    }
 }
 
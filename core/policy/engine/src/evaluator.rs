@@ -78,17 +78,23 @@ impl PolicyEvaluator {
     }
 
     pub fn evaluate(&self, context: &EvaluationContext, depth: usize) -> Result<PolicyDecision, PolicyError> {
+        {
+            let mut limiter = RATE_LIMITER.write();
+            limiter.check_rate_limit()?;
+        }
+
+        self.evaluate_unmetered(context, depth)
+    }
+
+    /// Same evaluation without the runtime rate limiter. Offline simulation only: replaying a
+    /// historical window would otherwise exhaust the live budget within seconds.
+    pub fn evaluate_unmetered(&self, context: &EvaluationContext, depth: usize) -> Result<PolicyDecision, PolicyError> {
         if depth > self.max_depth {
             return Err(PolicyError::MaxDepthExceeded(
                 format!("Maximum evaluation depth {} exceeded", self.max_depth)
             ));
         }
 
-        {
-            let mut limiter = RATE_LIMITER.write();
-            limiter.check_rate_limit()?;
-        }
-
         context.validate()?;
 
         debug!("Evaluating policies for alert: {} (depth: {})", context.alert_id, depth);
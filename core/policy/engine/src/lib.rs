@@ -17,6 +17,8 @@ pub mod policy;
 pub mod decision;
 pub mod context;
 pub mod matcher;
+pub mod quota;
+pub mod simulation;
 
 pub use engine::PolicyEngine;
 pub use errors::PolicyError;
@@ -24,6 +26,8 @@ pub use decision::{PolicyDecision, AllowedAction};
 pub use context::EvaluationContext;
 pub use precedence::PrecedenceRules;
 pub use policy::{PolicyRule, PolicyMatchCondition};
+pub use quota::{resolve_ingest_quotas, IngestQuota, IngestQuotaRule};
 pub use conflict::{ConflictDetector, ConflictResolver, PolicyConflict, ConflictType, ConflictResolution};
 pub use audit::{initialize_audit_logger, verify_audit_chain, log_decision};
+pub use simulation::{diff_decisions, transition_counts, DecisionChange, PolicyRef, PolicySimulator, SimulatedDecision, SimulatedOutcome, SimulationSummary};
 
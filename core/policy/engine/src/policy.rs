@@ -13,6 +13,7 @@ use tracing::{error, info, debug};
 
 use crate::errors::PolicyError;
 use crate::decision::AllowedAction;
+use crate::quota::IngestQuotaRule;
 
 // Helper function to sort JSON object keys recursively
 fn sort_json_value_keys(value: &mut serde_json::Value) {
@@ -78,6 +79,9 @@ pub struct Policy {
     pub match_conditions: Vec<PolicyMatchCondition>,
     pub decision: PolicyDecisionRule,
     pub required_approvals: Vec<String>,
+    /// Optional per-component-type ingestion budgets (see quota.rs)
+    #[serde(default)]
+    pub ingest_quotas: Vec<IngestQuotaRule>,
     pub signature: Option<String>,
     pub signature_hash: Option<String>,
 }
@@ -117,10 +121,21 @@ pub struct PolicyLoader {
     highest_versions: HashMap<String, String>,
     // Persist version state to file (in-memory is insufficient)
     version_state_path: String,
+    // False for simulation loaders: rollback is still checked, but a candidate bundle never advances the state
+    persist_version_state: bool,
 }
 
 impl PolicyLoader {
     pub fn new(policies_path: &str, trust_store_path: Option<&str>) -> Result<Self, PolicyError> {
+        Self::open(policies_path, trust_store_path, true)
+    }
+
+    /// Load and verify a policy bundle without recording its versions (policy simulation).
+    pub fn new_read_only(policies_path: &str, trust_store_path: Option<&str>) -> Result<Self, PolicyError> {
+        Self::open(policies_path, trust_store_path, false)
+    }
+
+    fn open(policies_path: &str, trust_store_path: Option<&str>, persist_version_state: bool) -> Result<Self, PolicyError> {
         let signature_verifier = PolicySignatureVerifier::new()
             .map_err(|e| PolicyError::ConfigurationError(
                 format!("Failed to initialize signature verifier: {}", e)
@@ -158,6 +173,7 @@ impl PolicyLoader {
             policies_path: policies_path.to_string(),
             highest_versions,
             version_state_path,
+            persist_version_state,
         };
 
         loader.load_policies()?;
@@ -240,6 +256,10 @@ impl PolicyLoader {
     fn save_version_state(&self) -> Result<(), PolicyError> {
         use std::fs;
         use std::path::Path;
+
+        if !self.persist_version_state {
+            return Ok(());
+        }
         
         let path_obj = Path::new(&self.version_state_path);
         
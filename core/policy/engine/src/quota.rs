@@ -0,0 +1,71 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_policy/engine/src/quota.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Ingestion quotas - per-component-type event budgets declared in signed policies and resolved deterministically
+
+#![cfg(feature = "future-policy")]
+
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::errors::PolicyError;
+use crate::policy::Policy;
+
+/// Quota entry as written in a policy file (`ingest_quotas:`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IngestQuotaRule {
+    /// Producer type as ingest labels it (linux_agent, dpi_probe, ...)
+    pub component_type: String,
+    /// Events accepted per component instance per minute
+    pub events_per_minute: u64,
+}
+
+/// Effective quota for one component type, with the policy it came from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IngestQuota {
+    pub component_type: String,
+    pub events_per_minute: u64,
+    pub policy_id: String,
+    pub policy_version: String,
+}
+
+/// Resolve the effective quota per component type across enabled policies.
+///
+/// Highest policy priority wins; at equal priority the lower (stricter) budget wins, then the
+/// lower policy id, so the result never depends on load order. A zero budget is rejected.
+pub fn resolve_ingest_quotas(policies: &[&Policy]) -> Result<Vec<IngestQuota>, PolicyError> {
+    let mut resolved: BTreeMap<String, (u32, IngestQuota)> = BTreeMap::new();
+
+    for policy in policies.iter().filter(|p| p.enabled) {
+        for rule in &policy.ingest_quotas {
+            if rule.component_type.is_empty() || rule.events_per_minute == 0 {
+                return Err(PolicyError::ConfigurationError(format!(
+                    "Policy {} has invalid ingest quota (component_type='{}', events_per_minute={})",
+                    policy.id, rule.component_type, rule.events_per_minute
+                )));
+            }
+            let candidate = IngestQuota {
+                component_type: rule.component_type.clone(),
+                events_per_minute: rule.events_per_minute,
+                policy_id: policy.id.clone(),
+                policy_version: policy.version.clone(),
+            };
+            let replace = match resolved.get(&rule.component_type) {
+                None => true,
+                Some((priority, current)) => {
+                    (policy.priority, std::cmp::Reverse(candidate.events_per_minute), std::cmp::Reverse(&candidate.policy_id))
+                        > (*priority, std::cmp::Reverse(current.events_per_minute), std::cmp::Reverse(&current.policy_id))
+                }
+            };
+            if replace {
+                resolved.insert(rule.component_type.clone(), (policy.priority, candidate));
+            }
+        }
+    }
+
+    let quotas: Vec<IngestQuota> = resolved.into_values().map(|(_, q)| q).collect();
+    for q in &quotas {
+        info!("Ingest quota: {} = {} events/min (policy {} v{})", q.component_type, q.events_per_minute, q.policy_id, q.policy_version);
+    }
+    Ok(quotas)
+}
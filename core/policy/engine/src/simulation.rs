@@ -0,0 +1,224 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_policy/engine/src/simulation.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Policy simulation - evaluates a candidate policy bundle against historical alerts without audit logging, version state updates or enforcement output
+
+#![cfg(feature = "future-policy")]
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use serde::Serialize;
+use tracing::{error, info};
+
+use crate::compiler::PolicyCompiler;
+use crate::context::EvaluationContext;
+use crate::decision::AllowedAction;
+use crate::errors::PolicyError;
+use crate::evaluator::PolicyEvaluator;
+use crate::policy::PolicyLoader;
+
+/// Operational meaning of a simulated decision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SimulatedOutcome {
+    /// Deny, block, quarantine or isolate
+    Blocked,
+    /// Monitor, escalate or require approval
+    Alerted,
+    Allowed,
+    /// Several policies matched at the same priority (the live engine refuses to decide)
+    Ambiguous,
+    /// Context could not be evaluated
+    Error,
+}
+
+impl SimulatedOutcome {
+    pub fn of(action: &AllowedAction) -> Self {
+        match action {
+            AllowedAction::Deny | AllowedAction::Block | AllowedAction::Quarantine | AllowedAction::Isolate => {
+                SimulatedOutcome::Blocked
+            }
+            AllowedAction::Monitor | AllowedAction::Escalate | AllowedAction::RequireApproval => SimulatedOutcome::Alerted,
+            AllowedAction::Allow => SimulatedOutcome::Allowed,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SimulatedOutcome::Blocked => "blocked",
+            SimulatedOutcome::Alerted => "alerted",
+            SimulatedOutcome::Allowed => "allowed",
+            SimulatedOutcome::Ambiguous => "ambiguous",
+            SimulatedOutcome::Error => "error",
+        }
+    }
+}
+
+/// Policy id of the live engine's fallback when nothing matches.
+pub const DEFAULT_DENY_POLICY_ID: &str = "default_deny";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SimulatedDecision {
+    pub alert_id: String,
+    pub outcome: SimulatedOutcome,
+    pub action: Option<AllowedAction>,
+    pub policy_id: Option<String>,
+    pub policy_version: Option<String>,
+    /// Ambiguity or evaluation error message
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PolicyRef {
+    pub id: String,
+    pub version: String,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SimulationSummary {
+    pub evaluated: usize,
+    pub blocked: usize,
+    pub alerted: usize,
+    pub allowed: usize,
+    pub ambiguous: usize,
+    pub errors: usize,
+    /// Alerts no policy matched (included in `blocked`)
+    pub default_deny: usize,
+    pub by_policy: BTreeMap<String, usize>,
+    pub by_action: BTreeMap<String, usize>,
+}
+
+impl SimulationSummary {
+    pub fn record(&mut self, d: &SimulatedDecision) {
+        self.evaluated += 1;
+        match d.outcome {
+            SimulatedOutcome::Blocked => self.blocked += 1,
+            SimulatedOutcome::Alerted => self.alerted += 1,
+            SimulatedOutcome::Allowed => self.allowed += 1,
+            SimulatedOutcome::Ambiguous => self.ambiguous += 1,
+            SimulatedOutcome::Error => self.errors += 1,
+        }
+        if let Some(policy_id) = &d.policy_id {
+            if policy_id == DEFAULT_DENY_POLICY_ID {
+                self.default_deny += 1;
+            }
+            *self.by_policy.entry(policy_id.clone()).or_insert(0) += 1;
+        }
+        if let Some(action) = &d.action {
+            *self.by_action.entry(format!("{:?}", action)).or_insert(0) += 1;
+        }
+    }
+}
+
+/// One alert decided differently by the candidate bundle.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DecisionChange {
+    pub alert_id: String,
+    pub baseline_outcome: SimulatedOutcome,
+    pub candidate_outcome: SimulatedOutcome,
+    pub baseline_action: Option<AllowedAction>,
+    pub candidate_action: Option<AllowedAction>,
+    pub baseline_policy_id: Option<String>,
+    pub candidate_policy_id: Option<String>,
+}
+
+/// Alerts whose outcome, action or deciding policy differ between two runs (keyed by alert_id,
+/// in baseline order). Alerts present in only one run are ignored.
+pub fn diff_decisions(baseline: &[SimulatedDecision], candidate: &[SimulatedDecision]) -> Vec<DecisionChange> {
+    let cand: HashMap<&str, &SimulatedDecision> = candidate.iter().map(|d| (d.alert_id.as_str(), d)).collect();
+    baseline
+        .iter()
+        .filter_map(|b| {
+            let c = cand.get(b.alert_id.as_str())?;
+            if b.outcome == c.outcome && b.action == c.action && b.policy_id == c.policy_id {
+                return None;
+            }
+            Some(DecisionChange {
+                alert_id: b.alert_id.clone(),
+                baseline_outcome: b.outcome,
+                candidate_outcome: c.outcome,
+                baseline_action: b.action.clone(),
+                candidate_action: c.action.clone(),
+                baseline_policy_id: b.policy_id.clone(),
+                candidate_policy_id: c.policy_id.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Outcome transitions ("allowed->blocked") with their counts.
+pub fn transition_counts(changes: &[DecisionChange]) -> BTreeMap<String, usize> {
+    let mut out = BTreeMap::new();
+    for c in changes {
+        let key = format!("{}->{}", c.baseline_outcome.as_str(), c.candidate_outcome.as_str());
+        *out.entry(key).or_insert(0) += 1;
+    }
+    out
+}
+
+/// Evaluates alerts against one policy bundle exactly like the live engine, minus its side
+/// effects: no audit log, no version-state update, no rate limit, and no decision leaves the process.
+pub struct PolicySimulator {
+    loader: Arc<PolicyLoader>,
+    evaluator: PolicyEvaluator,
+}
+
+impl PolicySimulator {
+    /// Same admission rules as `PolicyEngine::new`: every policy signed and compilable, no rollback.
+    pub fn new(policies_path: &str, trust_store_path: Option<&str>) -> Result<Self, PolicyError> {
+        let loader = Arc::new(PolicyLoader::new_read_only(policies_path, trust_store_path)?);
+        let compiler = PolicyCompiler::new();
+        for policy in loader.get_all_policies() {
+            if policy.signature.is_none() {
+                error!("Unsigned policy found in simulation bundle: {}", policy.id);
+                return Err(PolicyError::UnsignedPolicy(policy.id.clone()));
+            }
+            compiler.compile(policy)?;
+        }
+        let evaluator = PolicyEvaluator::new(loader.clone())?;
+        info!("Policy simulator loaded {} polic(ies) from {}", loader.get_all_policies().len(), policies_path);
+        Ok(Self { loader, evaluator })
+    }
+
+    /// Policies in the bundle, highest priority first.
+    pub fn bundle(&self) -> Vec<PolicyRef> {
+        self.loader
+            .get_all_policies()
+            .into_iter()
+            .map(|p| PolicyRef { id: p.id.clone(), version: p.version.clone(), enabled: p.enabled })
+            .collect()
+    }
+
+    pub fn simulate(&self, context: &EvaluationContext) -> SimulatedDecision {
+        let mut out = SimulatedDecision {
+            alert_id: context.alert_id.clone(),
+            outcome: SimulatedOutcome::Error,
+            action: None,
+            policy_id: None,
+            policy_version: None,
+            detail: None,
+        };
+        match self.evaluator.evaluate_unmetered(context, 0) {
+            Ok(decision) => {
+                out.outcome = SimulatedOutcome::of(&decision.decision);
+                out.action = Some(decision.decision);
+                out.policy_id = Some(decision.policy_id);
+                out.policy_version = Some(decision.policy_version);
+            }
+            Err(PolicyError::NoMatchingPolicy(_)) => {
+                out.outcome = SimulatedOutcome::Blocked;
+                out.action = Some(AllowedAction::Deny);
+                out.policy_id = Some(DEFAULT_DENY_POLICY_ID.to_string());
+            }
+            Err(PolicyError::PolicyAmbiguity(msg)) => {
+                out.outcome = SimulatedOutcome::Ambiguous;
+                out.detail = Some(msg);
+            }
+            Err(e) => {
+                out.detail = Some(e.to_string());
+            }
+        }
+        out
+    }
+}
@@ -0,0 +1,58 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_policy/tests/ingest_quota_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Runtime tests for ingestion quota resolution across policies
+
+use policy::policy::Policy;
+use policy::resolve_ingest_quotas;
+
+fn policy(id: &str, priority: u32, enabled: bool, quotas: &str) -> Policy {
+    serde_yaml::from_str(&format!(
+        r#"
+id: {id}
+version: 1.0.0
+name: {id}
+description: test
+enabled: {enabled}
+priority: {priority}
+match_conditions: []
+decision:
+  action: monitor
+  allowed_actions: [monitor]
+  reasoning: test
+required_approvals: []
+{quotas}
+signature: sig
+signature_hash: null
+"#
+    ))
+    .unwrap()
+}
+
+#[test]
+fn test_policy_without_quotas_still_parses() {
+    let p = policy("plain", 10, true, "");
+    assert!(p.ingest_quotas.is_empty());
+    assert!(resolve_ingest_quotas(&[&p]).unwrap().is_empty());
+}
+
+#[test]
+fn test_higher_priority_quota_wins_then_stricter() {
+    let low = policy("low", 10, true, "ingest_quotas:\n- {component_type: linux_agent, events_per_minute: 100}");
+    let high = policy("high", 50, true, "ingest_quotas:\n- {component_type: linux_agent, events_per_minute: 5000}\n- {component_type: dpi_probe, events_per_minute: 900}");
+    let tie = policy("tie", 50, true, "ingest_quotas:\n- {component_type: dpi_probe, events_per_minute: 600}");
+    let disabled = policy("off", 99, false, "ingest_quotas:\n- {component_type: linux_agent, events_per_minute: 1}");
+
+    let forward = resolve_ingest_quotas(&[&low, &high, &tie, &disabled]).unwrap();
+    let reverse = resolve_ingest_quotas(&[&disabled, &tie, &high, &low]).unwrap();
+    assert_eq!(forward, reverse);
+
+    assert_eq!(forward.len(), 2);
+    assert_eq!((forward[0].component_type.as_str(), forward[0].events_per_minute, forward[0].policy_id.as_str()), ("dpi_probe", 600, "tie"));
+    assert_eq!((forward[1].component_type.as_str(), forward[1].events_per_minute, forward[1].policy_id.as_str()), ("linux_agent", 5000, "high"));
+}
+
+#[test]
+fn test_zero_quota_rejected() {
+    let p = policy("zero", 10, true, "ingest_quotas:\n- {component_type: linux_agent, events_per_minute: 0}");
+    assert!(resolve_ingest_quotas(&[&p]).is_err());
+}
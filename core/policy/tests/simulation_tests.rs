@@ -0,0 +1,83 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_policy/tests/simulation_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Runtime tests for policy simulation outcome classification, summaries and baseline diffs
+
+use policy::{diff_decisions, transition_counts, AllowedAction, SimulatedDecision, SimulatedOutcome, SimulationSummary};
+
+fn decided(alert_id: &str, action: AllowedAction, policy_id: &str) -> SimulatedDecision {
+    SimulatedDecision {
+        alert_id: alert_id.to_string(),
+        outcome: SimulatedOutcome::of(&action),
+        action: Some(action),
+        policy_id: Some(policy_id.to_string()),
+        policy_version: Some("1.0.0".to_string()),
+        detail: None,
+    }
+}
+
+fn ambiguous(alert_id: &str) -> SimulatedDecision {
+    SimulatedDecision {
+        alert_id: alert_id.to_string(),
+        outcome: SimulatedOutcome::Ambiguous,
+        action: None,
+        policy_id: None,
+        policy_version: None,
+        detail: Some("Multiple policies match with same priority: 2".to_string()),
+    }
+}
+
+#[test]
+fn test_actions_classify_into_outcomes() {
+    for a in [AllowedAction::Deny, AllowedAction::Block, AllowedAction::Quarantine, AllowedAction::Isolate] {
+        assert_eq!(SimulatedOutcome::of(&a), SimulatedOutcome::Blocked);
+    }
+    for a in [AllowedAction::Monitor, AllowedAction::Escalate, AllowedAction::RequireApproval] {
+        assert_eq!(SimulatedOutcome::of(&a), SimulatedOutcome::Alerted);
+    }
+    assert_eq!(SimulatedOutcome::of(&AllowedAction::Allow), SimulatedOutcome::Allowed);
+}
+
+#[test]
+fn test_summary_counts_default_deny_as_blocked() {
+    let mut summary = SimulationSummary::default();
+    for d in [
+        decided("a1", AllowedAction::Quarantine, "lateral_movement"),
+        decided("a2", AllowedAction::Deny, "default_deny"),
+        decided("a3", AllowedAction::Monitor, "persistence"),
+        ambiguous("a4"),
+    ] {
+        summary.record(&d);
+    }
+    assert_eq!(summary.evaluated, 4);
+    assert_eq!((summary.blocked, summary.alerted, summary.allowed, summary.ambiguous), (2, 1, 0, 1));
+    assert_eq!(summary.default_deny, 1);
+    assert_eq!(summary.by_policy.get("lateral_movement"), Some(&1));
+    assert_eq!(summary.by_action.get("Quarantine"), Some(&1));
+    assert!(!summary.by_policy.contains_key(""));
+}
+
+#[test]
+fn test_diff_reports_changed_decisions_only() {
+    let baseline = vec![
+        decided("a1", AllowedAction::Monitor, "persistence"),
+        decided("a2", AllowedAction::Block, "privilege_abuse"),
+        decided("a3", AllowedAction::Deny, "default_deny"),
+        decided("only_baseline", AllowedAction::Allow, "allow_all"),
+    ];
+    let candidate = vec![
+        decided("a3", AllowedAction::Escalate, "new_policy"),
+        decided("a2", AllowedAction::Block, "privilege_abuse"),
+        decided("a1", AllowedAction::Quarantine, "persistence"),
+    ];
+
+    let changes = diff_decisions(&baseline, &candidate);
+    assert_eq!(changes.len(), 2);
+    assert_eq!(changes[0].alert_id, "a1");
+    assert_eq!(changes[0].candidate_outcome, SimulatedOutcome::Blocked);
+    assert_eq!(changes[1].alert_id, "a3");
+    assert_eq!(changes[1].candidate_policy_id.as_deref(), Some("new_policy"));
+
+    let transitions = transition_counts(&changes);
+    assert_eq!(transitions.get("alerted->blocked"), Some(&1));
+    assert_eq!(transitions.get("blocked->alerted"), Some(&1));
+}
@@ -1,6 +1,6 @@
 // Path and File Name : /home/ransomeye/rebuild/core/policy/tools/sign_policies.rs
 // Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
-// Details of functionality of this file: Standalone tool to sign policy files using ring RSA-PSS-SHA256
+// Details of functionality of this file: Standalone tool to sign policy files using ring RSA-PSS-SHA256, or an RSA key held in a PKCS#11 token (HSM / SoftHSM)
 
 #![cfg(feature = "future-policy")]
 
@@ -13,10 +13,35 @@ use base64::{Engine as _, engine::general_purpose};
 use serde_yaml;
 use serde_json;
 
+/// Env prefix for an HSM-held policy root key (`RANSOMEYE_POLICY_PKCS11_MODULE`, ...)
+const POLICY_PKCS11_ENV_PREFIX: &str = "RANSOMEYE_POLICY_PKCS11";
+
+enum PolicySigningKey {
+    /// RSA-4096 private key, DER PKCS#8
+    Der(Vec<u8>),
+    /// RSA-4096 key selected by RANSOMEYE_POLICY_PKCS11_*
+    Pkcs11(crypto::pkcs11::Pkcs11Config),
+}
+
 fn sign_policy_content(
     policy_bytes: &[u8],
-    private_key_der: &[u8],
+    key: &PolicySigningKey,
 ) -> Result<(String, String), Box<dyn std::error::Error>> {
+    let mut hasher = Sha256::new();
+    hasher.update(policy_bytes);
+    let content_hash = hex::encode(hasher.finalize());
+    
+    let signature = match key {
+        PolicySigningKey::Der(private_key_der) => sign_with_der(policy_bytes, private_key_der)?,
+        PolicySigningKey::Pkcs11(config) => sign_with_pkcs11(policy_bytes, config)?,
+    };
+    
+    let signature_base64 = general_purpose::STANDARD.encode(&signature);
+    
+    Ok((signature_base64, content_hash))
+}
+
+fn sign_with_der(policy_bytes: &[u8], private_key_der: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
     let key_pair = RsaKeyPair::from_pkcs8(private_key_der)
         .map_err(|e| format!("Failed to load RSA key pair: {:?}", e))?;
     
@@ -29,10 +54,6 @@ fn sign_policy_content(
         ).into());
     }
     
-    let mut hasher = Sha256::new();
-    hasher.update(policy_bytes);
-    let content_hash = hex::encode(hasher.finalize());
-    
     let rng = SystemRandom::new();
     let mut signature = vec![0u8; modulus_len];
     
@@ -46,9 +67,45 @@ fn sign_policy_content(
         &mut signature,
     ).map_err(|e| format!("Failed to sign policy: {:?}", e))?;
     
-    let signature_base64 = general_purpose::STANDARD.encode(&signature);
+    Ok(signature)
+}
+
+#[cfg(feature = "pkcs11")]
+fn sign_with_pkcs11(
+    policy_bytes: &[u8],
+    config: &crypto::pkcs11::Pkcs11Config,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    use crypto::pkcs11::{Pkcs11Key, Pkcs11KeyKind};
     
-    Ok((signature_base64, content_hash))
+    // FAIL-CLOSED: no fallback to a key file when the token is unavailable
+    let key = Pkcs11Key::open(config)
+        .map_err(|e| format!("Policy signing key unavailable: {}", e))?;
+    if key.kind() != Pkcs11KeyKind::RsaPssSha256 {
+        return Err(format!("Policy key '{}' is {:?}, expected RSA", config.key_label, key.kind()).into());
+    }
+    // CKM_SHA256_RSA_PKCS_PSS with MGF1-SHA256 and a 32-byte salt, same as ring's RSA_PSS_SHA256
+    let signature = key.sign(policy_bytes)
+        .map_err(|e| format!("Failed to sign policy: {}", e))?;
+    
+    // Verify key size (4096 bits = 512 bytes)
+    if signature.len() != 512 {
+        return Err(format!(
+            "Key size mismatch: expected 512 bytes (4096 bits), got {} bytes",
+            signature.len()
+        ).into());
+    }
+    Ok(signature)
+}
+
+#[cfg(not(feature = "pkcs11"))]
+fn sign_with_pkcs11(
+    _policy_bytes: &[u8],
+    config: &crypto::pkcs11::Pkcs11Config,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    Err(format!(
+        "Policy key '{}' is configured on a PKCS#11 token but sign_policies was built without the pkcs11 feature",
+        config.key_label
+    ).into())
 }
 
 // Helper: sort JSON keys deterministically (must match policy engine canonicalization)
@@ -88,10 +145,12 @@ fn canonicalize_policy_value_for_signing(policy_value: &serde_yaml::Value) -> Re
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args: Vec<String> = std::env::args().collect();
     
+    let pkcs11 = crypto::pkcs11::Pkcs11Config::from_env(POLICY_PKCS11_ENV_PREFIX)?;
+    
     // Support both old format (positional) and new format (flags)
     let (private_key_path, policy_path, out_path) = if args.len() >= 3 && !args[1].starts_with('-') {
         // Old format: <private_key> <policy> [out]
-        (Path::new(&args[1]), Path::new(&args[2]), None)
+        (Some(Path::new(&args[1])), Path::new(&args[2]), None)
     } else {
         // New format: --private-key <key> --policy <policy> [--out <out>]
         let mut private_key: Option<&str> = None;
@@ -135,20 +194,31 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         }
         
-        if private_key.is_none() || policy.is_none() {
+        if (private_key.is_none() && pkcs11.is_none()) || policy.is_none() {
             eprintln!("Usage: {} --private-key <key> --policy <policy> [--out <out>]", args[0]);
             eprintln!("   or: {} <private_key> <policy> [out]", args[0]);
             eprintln!("  --private-key, -k: Path to RSA-4096 private key in DER format (PKCS#8)");
             eprintln!("  --policy, -p: Path to policy YAML file to sign");
             eprintln!("  --out, -o: Optional output path (default: same as policy file)");
+            eprintln!("  Without --private-key, the key comes from a PKCS#11 token configured through");
+            eprintln!("  {0}_MODULE, {0}_TOKEN_LABEL, {0}_KEY_LABEL and {0}_PIN_FILE", POLICY_PKCS11_ENV_PREFIX);
             std::process::exit(1);
         }
         
-        (Path::new(private_key.unwrap()), Path::new(policy.unwrap()), out.map(Path::new))
+        (private_key.map(Path::new), Path::new(policy.unwrap()), out.map(Path::new))
     };
     
-    let private_key_der = fs::read(private_key_path)
-        .map_err(|e| format!("Failed to read private key: {}", e))?;
+    // An explicit --private-key wins over a configured token
+    let signing_key = match (private_key_path, pkcs11) {
+        (Some(path), _) => PolicySigningKey::Der(
+            fs::read(path).map_err(|e| format!("Failed to read private key: {}", e))?,
+        ),
+        (None, Some(config)) => {
+            println!("Using PKCS#11 key '{}' on token '{}'", config.key_label, config.token_label);
+            PolicySigningKey::Pkcs11(config)
+        }
+        (None, None) => return Err("No private key or PKCS#11 token configured".into()),
+    };
     
     println!("Signing policy: {}", policy_path.display());
     
@@ -182,7 +252,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let policy_bytes_raw = canonical.as_bytes();
     
     // Sign the policy using RSA-PSS-SHA256 (matches verification algorithm RSA_PSS_2048_8192_SHA256)
-    let (signature_base64, hash) = sign_policy_content(policy_bytes_raw, &private_key_der)?;
+    let (signature_base64, hash) = sign_policy_content(policy_bytes_raw, &signing_key)?;
     
     // Create .payload and .sig files (for isolated verification testing)
     let payload_path = policy_path.with_extension("yaml.payload");
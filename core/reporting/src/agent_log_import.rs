@@ -0,0 +1,351 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_reporting/src/agent_log_import.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Sealed agent log import - seals the hash-chained, signed log segments spooled by ingest into the evidence store and re-verifies an agent's log history there (line chains, seals, gaps, forks and signer key changes)
+
+#![cfg(feature = "future-reporting")]
+
+/*
+ * Sealed Agent Log Import
+ *
+ * Ingest spools every log segment a Linux agent shipped as <segment_id>.json (see
+ * core/ingest/src/agent_log.rs for the hash chain and seal). `import-agent-logs` seals each
+ * spooled segment into its own evidence bundle:
+ *
+ *   source / source_type   linux_agent / agent_log_segment
+ *   timestamp              sealed_at of the segment
+ *   data                   the spool entry: agent, continuity seen by ingest and the segment with
+ *                          its lines, hashes, signer key and signature exactly as shipped
+ *   metadata               segment_id, agent_id, chain_id, segment_index, continuity
+ *
+ * A segment whose lines or seal do not verify is moved to <spool>/rejected/ and never sealed.
+ *
+ * `verify-agent-logs` re-verifies every sealed segment of an agent and walks each chain in index
+ * order: a missing index is a gap (reported, the history has a hole), while two segments that do
+ * not link, a segment 0 off the genesis or a signer key change within a chain are problems.
+ */
+
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use ring::signature::{UnparsedPublicKey, ED25519};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::collector::{CollectedEvidence, EvidenceCollector};
+use crate::errors::ReportingError;
+use crate::evidence_store::EvidenceStore;
+
+/// Evidence source of imported segments
+pub const AGENT_LOG_SOURCE: &str = "linux_agent";
+/// Evidence source_type of imported segments
+pub const AGENT_LOG_SOURCE_TYPE: &str = "agent_log_segment";
+/// Spool subdirectory for segments that failed verification
+pub const REJECTED_DIR: &str = "rejected";
+
+const SEP: &str = "\x1f";
+const GENESIS_DOMAIN: &str = "ransomeye-log-genesis-v1";
+const LINE_DOMAIN: &str = "ransomeye-log-line-v1";
+const SEAL_DOMAIN: &str = "ransomeye-log-seal-v1";
+
+/// A sealed segment as shipped by the agent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogSegment {
+    pub chain_id: String,
+    pub boot_id: String,
+    pub segment_index: u64,
+    pub first_line: u64,
+    pub prev_hash: String,
+    pub head_hash: String,
+    /// RFC 3339, signed as sent
+    pub sealed_at: String,
+    pub segments_dropped_before: u64,
+    pub lines: Vec<String>,
+    /// Hex Ed25519 public key
+    pub signer_key: String,
+    /// Base64 Ed25519 signature of the seal digest
+    pub signature: String,
+}
+
+/// Spool entry written by ingest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpoolEntry {
+    pub segment_id: Uuid,
+    pub agent_id: Uuid,
+    pub agent_component_id: String,
+    pub received_at: DateTime<Utc>,
+    /// genesis, linked or gap as seen by ingest on arrival
+    pub continuity: String,
+    pub segment: LogSegment,
+}
+
+/// Genesis hash of a chain
+pub fn genesis_hash(boot_id: &str, chain_id: &str) -> String {
+    hex::encode(Sha256::digest([GENESIS_DOMAIN, boot_id, chain_id].join(SEP).as_bytes()))
+}
+
+/// Hash of a line linked to the previous hash
+pub fn line_hash(prev_hash: &str, line: &str) -> String {
+    hex::encode(Sha256::digest([LINE_DOMAIN, prev_hash, line].join(SEP).as_bytes()))
+}
+
+impl LogSegment {
+    /// Digest the signature covers
+    pub fn digest(&self) -> Vec<u8> {
+        let fields = [
+            SEAL_DOMAIN.to_string(),
+            self.chain_id.clone(),
+            self.boot_id.clone(),
+            self.segment_index.to_string(),
+            self.first_line.to_string(),
+            self.lines.len().to_string(),
+            self.prev_hash.clone(),
+            self.head_hash.clone(),
+            self.sealed_at.clone(),
+            self.segments_dropped_before.to_string(),
+        ];
+        Sha256::digest(fields.join(SEP).as_bytes()).to_vec()
+    }
+
+    pub fn sealed_at(&self) -> Result<DateTime<Utc>, String> {
+        DateTime::parse_from_rfc3339(&self.sealed_at)
+            .map(|t| t.with_timezone(&Utc))
+            .map_err(|_| format!("sealed_at '{}' is not RFC 3339", self.sealed_at))
+    }
+
+    /// Lines hash from prev_hash to head_hash and the seal verifies under signer_key.
+    pub fn verify(&self) -> Result<(), String> {
+        if self.lines.is_empty() {
+            return Err("segment has no lines".to_string());
+        }
+        self.sealed_at()?;
+        let head = self.lines.iter().fold(self.prev_hash.clone(), |prev, line| line_hash(&prev, line));
+        if head != self.head_hash {
+            return Err("lines do not hash to head_hash (altered, missing or reordered lines)".to_string());
+        }
+        let key = hex::decode(&self.signer_key).map_err(|_| "signer_key is not hex".to_string())?;
+        let signature = general_purpose::STANDARD
+            .decode(&self.signature)
+            .map_err(|_| "signature is not base64".to_string())?;
+        UnparsedPublicKey::new(&ED25519, &key)
+            .verify(&self.digest(), &signature)
+            .map_err(|_| "seal signature does not verify under signer_key".to_string())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportedSegment {
+    pub segment_id: Uuid,
+    pub agent_id: Uuid,
+    pub chain_id: String,
+    pub segment_index: u64,
+    pub bundle_id: String,
+    pub evidence_id: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct RejectedSegment {
+    /// Spool file stem (the segment id)
+    pub entry: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ImportOutcome {
+    pub imported: Vec<ImportedSegment>,
+    pub rejected: Vec<RejectedSegment>,
+}
+
+fn load_entry(path: &Path) -> Result<SpoolEntry, String> {
+    let entry: SpoolEntry = serde_json::from_slice(&fs::read(path).map_err(|e| e.to_string())?)
+        .map_err(|e| format!("unreadable spool entry: {}", e))?;
+    if path.file_stem().and_then(|s| s.to_str()) != Some(entry.segment_id.to_string().as_str()) {
+        return Err(format!("spool entry names segment {}", entry.segment_id));
+    }
+    entry.segment.verify()?;
+    Ok(entry)
+}
+
+fn evidence_for(collector: &EvidenceCollector, entry: &SpoolEntry) -> Result<CollectedEvidence, ReportingError> {
+    let sealed_at = entry.segment.sealed_at().map_err(ReportingError::InvalidTimestamp)?;
+    let metadata = HashMap::from([
+        ("segment_id".to_string(), entry.segment_id.to_string()),
+        ("agent_id".to_string(), entry.agent_id.to_string()),
+        ("chain_id".to_string(), entry.segment.chain_id.clone()),
+        ("segment_index".to_string(), entry.segment.segment_index.to_string()),
+        ("continuity".to_string(), entry.continuity.clone()),
+    ]);
+    collector.collect_with_timestamp(
+        AGENT_LOG_SOURCE,
+        AGENT_LOG_SOURCE_TYPE,
+        serde_json::to_value(entry)?,
+        sealed_at,
+        None,
+        metadata,
+    )
+}
+
+fn reject(spool_dir: &Path, path: &Path, reason: String) -> Result<RejectedSegment, ReportingError> {
+    let rejected_dir = spool_dir.join(REJECTED_DIR);
+    fs::create_dir_all(&rejected_dir)?;
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default().to_string();
+    if let Some(name) = path.file_name() {
+        fs::rename(path, rejected_dir.join(name))?;
+    }
+    warn!("Rejected spooled log segment {}: {}", stem, reason);
+    Ok(RejectedSegment { entry: stem, reason })
+}
+
+/// Seal every spooled segment into the evidence store (one bundle per segment).
+/// Store errors abort the import; the segment being imported stays in the spool.
+pub fn import_segments(
+    store: &EvidenceStore,
+    collector: &EvidenceCollector,
+    engine_version: &str,
+    policy_version: &str,
+    spool_dir: &Path,
+) -> Result<ImportOutcome, ReportingError> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(spool_dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && p.extension().and_then(|e| e.to_str()) == Some("json"))
+        .collect();
+    entries.sort();
+
+    let mut outcome = ImportOutcome::default();
+    for path in entries {
+        let entry = match load_entry(&path) {
+            Ok(entry) => entry,
+            Err(reason) => {
+                outcome.rejected.push(reject(spool_dir, &path, reason)?);
+                continue;
+            }
+        };
+        let evidence = evidence_for(collector, &entry)?;
+        let evidence_id = evidence.evidence_id.clone();
+        let bundle_id = store.create_bundle(engine_version, policy_version)?;
+        store.add_evidence(&bundle_id, evidence)?;
+        store.seal_bundle(&bundle_id)?;
+        fs::remove_file(&path)?;
+        info!(
+            "Imported log segment {} | bundle={} | agent={} | chain={} | index={} | lines={} | continuity={}",
+            entry.segment_id,
+            bundle_id,
+            entry.agent_id,
+            entry.segment.chain_id,
+            entry.segment.segment_index,
+            entry.segment.lines.len(),
+            entry.continuity
+        );
+        outcome.imported.push(ImportedSegment {
+            segment_id: entry.segment_id,
+            agent_id: entry.agent_id,
+            chain_id: entry.segment.chain_id,
+            segment_index: entry.segment.segment_index,
+            bundle_id,
+            evidence_id,
+        });
+    }
+    Ok(outcome)
+}
+
+/// Verification of one chain of an agent.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChainReport {
+    pub chain_id: String,
+    pub boot_id: String,
+    pub segments: u64,
+    pub lines: u64,
+    /// Highest segment index sealed
+    pub last_index: u64,
+    /// Missing segment index ranges (inclusive)
+    pub gaps: Vec<(u64, u64)>,
+    /// Segments the agent reported dropping before shipping
+    pub dropped_by_agent: u64,
+    /// Forks, seals that no longer verify, genesis mismatches and signer key changes
+    pub problems: Vec<String>,
+}
+
+impl ChainReport {
+    /// Every sealed segment verifies and links to its neighbours (gaps aside)
+    pub fn is_intact(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Re-verify every sealed log segment of `agent_id`, one report per chain (ordered by chain id).
+pub fn verify_agent_logs(store: &EvidenceStore, agent_id: &str) -> Result<Vec<ChainReport>, ReportingError> {
+    let mut chains: BTreeMap<String, Vec<LogSegment>> = BTreeMap::new();
+    let mut unreadable = Vec::new();
+    for evidence in store
+        .get_all_bundles()
+        .into_iter()
+        .filter(|b| b.is_sealed)
+        .flat_map(|b| b.evidence_items)
+        .filter(|e| {
+            e.source_type == AGENT_LOG_SOURCE_TYPE
+                && e.metadata.get("agent_id").is_some_and(|id| id.eq_ignore_ascii_case(agent_id))
+        })
+    {
+        match serde_json::from_value::<SpoolEntry>(evidence.data.clone()) {
+            Ok(entry) => chains.entry(entry.segment.chain_id.clone()).or_default().push(entry.segment),
+            Err(e) => unreadable.push(format!("evidence {}: {}", evidence.evidence_id, e)),
+        }
+    }
+    if chains.is_empty() && unreadable.is_empty() {
+        return Err(ReportingError::MissingEvidence(format!("No sealed log segments of agent {}", agent_id)));
+    }
+    if !unreadable.is_empty() {
+        return Err(ReportingError::EvidenceCorrupted(format!("Unreadable log segment evidence: {}", unreadable.join("; "))));
+    }
+
+    Ok(chains.into_iter().map(|(chain_id, segments)| verify_chain(chain_id, segments)).collect())
+}
+
+fn verify_chain(chain_id: String, mut segments: Vec<LogSegment>) -> ChainReport {
+    segments.sort_by_key(|s| s.segment_index);
+    let mut report = ChainReport { chain_id, boot_id: segments[0].boot_id.clone(), ..Default::default() };
+    let signer_key = segments[0].signer_key.clone();
+    let mut previous: Option<&LogSegment> = None;
+    for segment in &segments {
+        let index = segment.segment_index;
+        // The same segment imported twice (resent by the agent) counts once
+        if previous.is_some_and(|prev| prev == segment) {
+            continue;
+        }
+        report.segments += 1;
+        report.lines += segment.lines.len() as u64;
+        report.last_index = index;
+        report.dropped_by_agent += segment.segments_dropped_before;
+        if let Err(e) = segment.verify() {
+            report.problems.push(format!("segment {}: {}", index, e));
+        }
+        if segment.signer_key != signer_key {
+            report.problems.push(format!("segment {}: signer key changed to {}", index, segment.signer_key));
+        }
+        if segment.boot_id != report.boot_id {
+            report.problems.push(format!("segment {}: boot id changed to {}", index, segment.boot_id));
+        }
+        match previous {
+            None if index == 0 => {
+                if segment.prev_hash != genesis_hash(&segment.boot_id, &report.chain_id) {
+                    report.problems.push("segment 0 does not start at the chain genesis".to_string());
+                }
+            }
+            None => report.gaps.push((0, index - 1)),
+            Some(prev) if prev.segment_index == index => {
+                report.problems.push(format!("segment {} sealed twice with different lines (fork)", index));
+            }
+            Some(prev) if prev.segment_index + 1 == index => {
+                if prev.head_hash != segment.prev_hash {
+                    report.problems.push(format!("segment {} does not follow segment {} (fork)", index, prev.segment_index));
+                }
+            }
+            Some(prev) => report.gaps.push((prev.segment_index + 1, index - 1)),
+        }
+        previous = Some(segment);
+    }
+    report
+}
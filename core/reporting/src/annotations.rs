@@ -0,0 +1,194 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_reporting/src/annotations.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Investigation annotations - loads analyst notes with their revision history from the control-plane export and selects the notes linked to a report's evidence and timeline
+
+#![cfg(feature = "future-reporting")]
+
+/*
+ * Investigation Annotations
+ *
+ * Analysts annotate incidents, detections and timeline entries through the ingest admin API
+ * (POST /admin/annotations, POST /admin/annotations/edit). Edits never replace a note: each
+ * one is a new revision with its author, time and reason. GET /admin/annotations exports the
+ * notes with every revision, and a report built with that export carries the notes linked to
+ * its evidence:
+ *
+ *   incident        an evidence item names the incident in incident_id
+ *   detection       an evidence item names the detection in detection_id
+ *   timeline_entry  the entry id is an evidence_id of the report's evidence or timeline
+ *
+ * Links are read from evidence metadata and top-level string fields of evidence data, as for
+ * legal holds. The report keeps the full history: the JSON export has every revision and the
+ * HTML/CSV exports list them under the current text. An export whose history is not complete
+ * (revisions not numbered 1..n, an edit without a reason) is refused rather than reported.
+ */
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::errors::ReportingError;
+use crate::evidence_store::EvidenceBundle;
+use crate::timeline::ForensicTimeline;
+
+/// What a note is attached to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnnotationSubject {
+    Incident,
+    Detection,
+    TimelineEntry,
+}
+
+impl AnnotationSubject {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AnnotationSubject::Incident => "incident",
+            AnnotationSubject::Detection => "detection",
+            AnnotationSubject::TimelineEntry => "timeline_entry",
+        }
+    }
+}
+
+/// One version of a note; revision 1 is the original text.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnnotationRevision {
+    pub revision: u32,
+    pub body: String,
+    pub author: String,
+    pub edited_at: DateTime<Utc>,
+    #[serde(default)]
+    pub edit_reason: Option<String>,
+}
+
+/// A note and its complete revision history, oldest first.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    pub annotation_id: String,
+    pub subject_type: AnnotationSubject,
+    pub subject_id: String,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    pub revisions: Vec<AnnotationRevision>,
+}
+
+impl Annotation {
+    /// Latest revision (validated non-empty at load).
+    pub fn current(&self) -> &AnnotationRevision {
+        self.revisions.last().expect("annotation without revisions")
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.revisions.is_empty() {
+            return Err("no revisions".to_string());
+        }
+        for (i, revision) in self.revisions.iter().enumerate() {
+            if revision.revision as usize != i + 1 {
+                return Err(format!("revision {} found where {} was expected", revision.revision, i + 1));
+            }
+            if (i == 0) != revision.edit_reason.is_none() {
+                return Err(format!("revision {} has an inconsistent edit_reason", revision.revision));
+            }
+        }
+        if self.revisions[0].author != self.created_by {
+            return Err("revision 1 author differs from created_by".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Annotation export (GET /admin/annotations).
+#[derive(Debug, Clone, Default)]
+pub struct Annotations {
+    items: Vec<Annotation>,
+}
+
+impl Annotations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn add(&mut self, annotation: Annotation) -> Result<(), ReportingError> {
+        annotation.validate().map_err(|e| {
+            ReportingError::InvalidConfiguration(format!("Annotation {}: {}", annotation.annotation_id, e))
+        })?;
+        self.items.push(annotation);
+        Ok(())
+    }
+
+    /// Load an annotation export: the list page ({"data": [...]}) or a bare array of notes.
+    /// Every note must carry its full history.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ReportingError> {
+        let path = path.as_ref();
+        let doc: serde_json::Value = serde_json::from_str(&fs::read_to_string(path)?)?;
+        let items = doc
+            .get("data")
+            .unwrap_or(&doc)
+            .as_array()
+            .ok_or_else(|| ReportingError::InvalidConfiguration(format!(
+                "Annotation export {} is not a list of annotations", path.display()
+            )))?;
+
+        let mut annotations = Self::new();
+        for item in items {
+            let annotation: Annotation = serde_json::from_value(item.clone()).map_err(|e| {
+                ReportingError::InvalidConfiguration(format!(
+                    "Annotation export {} has an invalid entry ({}): {}", path.display(), e, item
+                ))
+            })?;
+            annotations.add(annotation)?;
+        }
+        Ok(annotations)
+    }
+
+    /// Notes linked to the evidence or timeline entries of a report, oldest first.
+    pub fn linked(&self, bundles: &[EvidenceBundle], timeline: Option<&ForensicTimeline>) -> Vec<Annotation> {
+        if self.items.is_empty() {
+            return Vec::new();
+        }
+        let mut incidents = HashSet::new();
+        let mut detections = HashSet::new();
+        let mut entries: HashSet<String> = timeline
+            .map(|t| t.get_events().iter().map(|e| e.evidence_id.clone()).collect())
+            .unwrap_or_default();
+        for evidence in bundles.iter().flat_map(|b| &b.evidence_items) {
+            entries.insert(evidence.evidence_id.clone());
+            let data_fields = evidence
+                .data
+                .as_object()
+                .into_iter()
+                .flat_map(|o| o.iter())
+                .filter_map(|(k, v)| v.as_str().map(|v| (k.as_str(), v)));
+            for (key, value) in evidence.metadata.iter().map(|(k, v)| (k.as_str(), v.as_str())).chain(data_fields) {
+                match key {
+                    "incident_id" => incidents.insert(value.trim().to_lowercase()),
+                    "detection_id" => detections.insert(value.trim().to_lowercase()),
+                    _ => false,
+                };
+            }
+        }
+
+        let mut linked: Vec<Annotation> = self
+            .items
+            .iter()
+            .filter(|a| match a.subject_type {
+                AnnotationSubject::Incident => incidents.contains(&a.subject_id.to_lowercase()),
+                AnnotationSubject::Detection => detections.contains(&a.subject_id.to_lowercase()),
+                AnnotationSubject::TimelineEntry => entries.contains(&a.subject_id),
+            })
+            .cloned()
+            .collect();
+        linked.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.annotation_id.cmp(&b.annotation_id)));
+        linked
+    }
+}
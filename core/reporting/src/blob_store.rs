@@ -0,0 +1,260 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_reporting/src/blob_store.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Content-addressed blob store for evidence payloads - SHA-256 deduplication with reference counting, transparent zstd compression above a size threshold and hash verification on every read
+
+#![cfg(feature = "future-reporting")]
+
+/*
+ * Evidence Blob Store
+ *
+ * Evidence payloads are stored once per distinct content under <store>/blobs/<aa>/<sha256>, where
+ * sha256 is the digest of the uncompressed payload bytes. Payloads of at least the compression
+ * threshold are written zstd-compressed as <sha256>.zst when that is smaller. Every read
+ * decompresses and re-hashes; a mismatch is EvidenceCorrupted (fail-closed).
+ *
+ * Reference counts are not persisted: the evidence store rebuilds them from the sealed bundles
+ * when it opens, so a crash can never leave a stale count. A crash between writing a blob and
+ * writing the bundle that references it leaves an unreferenced blob, which costs space but
+ * never integrity; stats() reports those as orphaned.
+ */
+
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+use crate::errors::ReportingError;
+use crate::hasher::EvidenceHasher;
+
+/// Payloads at least this large are compressed (when compression helps).
+pub const DEFAULT_COMPRESSION_THRESHOLD: usize = 4096;
+const ZSTD_LEVEL: i32 = 3;
+const COMPRESSED_SUFFIX: &str = ".zst";
+
+#[derive(Debug, Clone, Copy, Default)]
+struct BlobEntry {
+    refs: u64,
+    /// Uncompressed size; 0 until the blob has been read or written in this process
+    logical_bytes: u64,
+    stored_bytes: u64,
+}
+
+/// Space accounting for the blob store.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StorageStats {
+    /// Distinct referenced payloads
+    pub unique_blobs: u64,
+    /// Evidence items referencing a blob
+    pub references: u64,
+    /// Payload bytes as if every reference were stored separately and uncompressed
+    pub logical_bytes: u64,
+    /// Uncompressed bytes of the distinct referenced payloads
+    pub unique_bytes: u64,
+    /// Bytes on disk for the distinct referenced payloads
+    pub stored_bytes: u64,
+    /// Blobs on disk that no bundle references
+    pub orphaned_blobs: u64,
+    pub orphaned_bytes: u64,
+}
+
+impl StorageStats {
+    pub fn dedup_saved_bytes(&self) -> u64 {
+        self.logical_bytes.saturating_sub(self.unique_bytes)
+    }
+
+    pub fn compression_saved_bytes(&self) -> u64 {
+        self.unique_bytes.saturating_sub(self.stored_bytes)
+    }
+
+    pub fn saved_bytes(&self) -> u64 {
+        self.logical_bytes.saturating_sub(self.stored_bytes)
+    }
+
+    /// Fraction of logical bytes not written to disk (0.0 - 1.0)
+    pub fn savings_ratio(&self) -> f64 {
+        if self.logical_bytes == 0 {
+            return 0.0;
+        }
+        self.saved_bytes() as f64 / self.logical_bytes as f64
+    }
+}
+
+pub struct BlobStore {
+    root: PathBuf,
+    compression_threshold: usize,
+    hasher: EvidenceHasher,
+    entries: Mutex<HashMap<String, BlobEntry>>,
+}
+
+impl BlobStore {
+    /// Open (creating if needed) the blob directory. Existing blobs start with zero references.
+    pub fn open(root: impl AsRef<Path>, compression_threshold: usize) -> Result<Self, ReportingError> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(&root)?;
+        let mut entries = HashMap::new();
+        for shard in fs::read_dir(&root)? {
+            let shard = shard?.path();
+            if !shard.is_dir() {
+                continue;
+            }
+            for blob in fs::read_dir(&shard)? {
+                let blob = blob?;
+                let name = blob.file_name().to_string_lossy().to_string();
+                let digest = name.strip_suffix(COMPRESSED_SUFFIX).unwrap_or(&name);
+                if !is_digest(digest) {
+                    continue;
+                }
+                entries.insert(
+                    digest.to_string(),
+                    BlobEntry { refs: 0, logical_bytes: 0, stored_bytes: blob.metadata()?.len() },
+                );
+            }
+        }
+        Ok(Self {
+            root,
+            compression_threshold,
+            hasher: EvidenceHasher::new(),
+            entries: Mutex::new(entries),
+        })
+    }
+
+    pub fn compression_threshold(&self) -> usize {
+        self.compression_threshold
+    }
+
+    /// Store a payload (or add a reference to the identical stored one). Returns its SHA-256.
+    pub fn put(&self, data: &[u8]) -> Result<String, ReportingError> {
+        let digest = self.hasher.hash_bytes(data);
+        let mut entries = self.entries.lock();
+        if let Some(entry) = entries.get_mut(&digest) {
+            entry.refs += 1;
+            entry.logical_bytes = data.len() as u64;
+            debug!("Deduplicated evidence payload {} ({} references)", digest, entry.refs);
+            return Ok(digest);
+        }
+
+        let (bytes, compressed) = self.encode(data)?;
+        let path = self.path(&digest, compressed);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, &bytes)?;
+        fs::rename(&tmp, &path)?;
+        entries.insert(
+            digest.clone(),
+            BlobEntry { refs: 1, logical_bytes: data.len() as u64, stored_bytes: bytes.len() as u64 },
+        );
+        Ok(digest)
+    }
+
+    /// Read and verify a payload.
+    pub fn get(&self, digest: &str) -> Result<Vec<u8>, ReportingError> {
+        if !is_digest(digest) {
+            return Err(ReportingError::EvidenceCorrupted(format!("Invalid blob reference '{}'", digest)));
+        }
+        let data = match fs::read(self.path(digest, true)) {
+            Ok(raw) => zstd::decode_all(raw.as_slice()).map_err(|e| {
+                ReportingError::EvidenceCorrupted(format!("Blob {} failed to decompress: {}", digest, e))
+            })?,
+            Err(e) if e.kind() == ErrorKind::NotFound => match fs::read(self.path(digest, false)) {
+                Ok(raw) => raw,
+                Err(e) if e.kind() == ErrorKind::NotFound => {
+                    return Err(ReportingError::MissingEvidence(format!("Blob {} not found", digest)));
+                }
+                Err(e) => return Err(e.into()),
+            },
+            Err(e) => return Err(e.into()),
+        };
+        let actual = self.hasher.hash_bytes(&data);
+        if actual != digest {
+            return Err(ReportingError::HashMismatch { expected: digest.to_string(), actual });
+        }
+        if let Some(entry) = self.entries.lock().get_mut(digest) {
+            entry.logical_bytes = data.len() as u64;
+        }
+        Ok(data)
+    }
+
+    /// Count one more reference to an existing blob (used when rebuilding counts on open).
+    pub fn retain(&self, digest: &str) {
+        self.entries.lock().entry(digest.to_string()).or_default().refs += 1;
+    }
+
+    /// Drop one reference; the blob is deleted with its last reference. Returns true if deleted.
+    pub fn release(&self, digest: &str) -> Result<bool, ReportingError> {
+        let mut entries = self.entries.lock();
+        let Some(entry) = entries.get_mut(digest) else {
+            return Ok(false);
+        };
+        entry.refs = entry.refs.saturating_sub(1);
+        if entry.refs > 0 {
+            return Ok(false);
+        }
+        entries.remove(digest);
+        for compressed in [true, false] {
+            match fs::remove_file(self.path(digest, compressed)) {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        debug!("Deleted evidence payload {} (no references left)", digest);
+        Ok(true)
+    }
+
+    /// Path of a stored blob, if present
+    pub fn locate(&self, digest: &str) -> Option<PathBuf> {
+        if !is_digest(digest) {
+            return None;
+        }
+        [true, false].into_iter().map(|c| self.path(digest, c)).find(|p| p.exists())
+    }
+    
+    pub fn references(&self, digest: &str) -> u64 {
+        self.entries.lock().get(digest).map(|e| e.refs).unwrap_or(0)
+    }
+
+    pub fn stats(&self) -> StorageStats {
+        let entries = self.entries.lock();
+        let mut stats = StorageStats::default();
+        for entry in entries.values() {
+            if entry.refs == 0 {
+                stats.orphaned_blobs += 1;
+                stats.orphaned_bytes += entry.stored_bytes;
+                continue;
+            }
+            stats.unique_blobs += 1;
+            stats.references += entry.refs;
+            stats.logical_bytes += entry.logical_bytes * entry.refs;
+            stats.unique_bytes += entry.logical_bytes;
+            stats.stored_bytes += entry.stored_bytes;
+        }
+        stats
+    }
+
+    fn encode(&self, data: &[u8]) -> Result<(Vec<u8>, bool), ReportingError> {
+        if data.len() >= self.compression_threshold {
+            let compressed = zstd::encode_all(data, ZSTD_LEVEL)?;
+            if compressed.len() < data.len() {
+                return Ok((compressed, true));
+            }
+        }
+        Ok((data.to_vec(), false))
+    }
+
+    fn path(&self, digest: &str, compressed: bool) -> PathBuf {
+        let name = if compressed {
+            format!("{}{}", digest, COMPRESSED_SUFFIX)
+        } else {
+            digest.to_string()
+        };
+        self.root.join(&digest[..2]).join(name)
+    }
+}
+
+fn is_digest(value: &str) -> bool {
+    value.len() == 64 && value.bytes().all(|b| b.is_ascii_hexdigit() && !b.is_ascii_uppercase())
+}
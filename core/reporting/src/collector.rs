@@ -32,6 +32,9 @@ pub struct CollectedEvidence {
     pub data: Value,
     pub metadata: HashMap<String, String>,
     pub integrity_hash: String,
+    /// Blob digests of raw payload chunks kept outside `data` (large images), in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chunks: Vec<String>,
 }
 
 impl EvidenceCollector {
@@ -92,6 +95,7 @@ impl EvidenceCollector {
             data,
             metadata,
             integrity_hash,
+            chunks: Vec::new(),
         })
     }
     
@@ -152,6 +156,7 @@ impl EvidenceCollector {
             data,
             metadata,
             integrity_hash,
+            chunks: Vec::new(),
         })
     }
 }
@@ -1,6 +1,6 @@
 // Path and File Name : /home/ransomeye/rebuild/core/reporting/src/deception_report.rs
 // Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
-// Details of functionality of this file: Deception event reporting - generates reports with timeline, host sequence, artifact touched, confidence score, attacker ID
+// Details of functionality of this file: Deception event reporting - generates reports with timeline, host sequence, artifact touched, confidence score, attacker ID, and per-decoy effectiveness from the deception analytics job
 
 use chrono::{DateTime, Utc};
 use serde::{Serialize, Deserialize};
@@ -39,6 +39,53 @@ pub struct DeceptionReport {
     pub attacker_sessions: Vec<AttackerSession>,
     pub confidence_scores: HashMap<String, f64>,
     pub summary: DeceptionReportSummary,
+    /// Per-decoy effectiveness (ransomeye_deception_analytics report), when loaded
+    #[serde(default)]
+    pub effectiveness: Option<DeceptionEffectiveness>,
+}
+
+/// Effectiveness of one decoy asset over the analytics window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecoyEffectiveness {
+    pub kind: String, // "honeyfile", "honeycredential", "fake_service"
+    pub key: String,
+    pub name: Option<String>,
+    pub deployed_at: Option<DateTime<Utc>>,
+    pub interactions: u64,
+    pub first_touch_at: Option<DateTime<Utc>>,
+    pub last_touch_at: Option<DateTime<Utc>>,
+    pub time_to_first_touch_secs: Option<i64>,
+    pub unique_agents: usize,
+    pub unique_users: usize,
+    pub unique_processes: usize,
+    pub unique_source_ips: usize,
+    pub detections: usize,
+    pub incidents: usize,
+}
+
+/// Deception analytics run (JSON written by ransomeye_deception_analytics)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeceptionEffectiveness {
+    pub generated_at: DateTime<Utc>,
+    pub window_start: DateTime<Utc>,
+    pub interactions: u64,
+    pub unattributed_interactions: u64,
+    pub touched_assets: usize,
+    pub untouched_assets: usize,
+    pub detections: usize,
+    pub incidents: usize,
+    /// Most detections first, then most interactions; untouched assets last
+    pub assets: Vec<DecoyEffectiveness>,
+}
+
+impl DeceptionEffectiveness {
+    /// Load an analytics report (e.g. /var/lib/ransomeye/deception-analytics/report.json)
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ReportingError> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| ReportingError::IoError(e))?;
+        serde_json::from_str(&json)
+            .map_err(|e| ReportingError::SerializationError(e))
+    }
 }
 
 /// Attacker session information
@@ -69,6 +116,7 @@ pub struct DeceptionReportSummary {
 /// Deception report builder
 pub struct DeceptionReportBuilder {
     events: Vec<DeceptionReportEvent>,
+    effectiveness: Option<DeceptionEffectiveness>,
 }
 
 impl DeceptionReportBuilder {
@@ -76,6 +124,7 @@ impl DeceptionReportBuilder {
     pub fn new() -> Self {
         Self {
             events: Vec::new(),
+            effectiveness: None,
         }
     }
     
@@ -84,6 +133,11 @@ impl DeceptionReportBuilder {
         self.events.push(event);
     }
     
+    /// Include per-decoy effectiveness from a deception analytics run
+    pub fn set_effectiveness(&mut self, effectiveness: DeceptionEffectiveness) {
+        self.effectiveness = Some(effectiveness);
+    }
+    
     /// Build deception report
     pub fn build(self) -> DeceptionReport {
         // Sort events by timestamp
@@ -190,6 +244,7 @@ impl DeceptionReportBuilder {
             attacker_sessions,
             confidence_scores,
             summary,
+            effectiveness: self.effectiveness,
         }
     }
     
@@ -255,6 +310,61 @@ impl DeceptionReportBuilder {
         Ok(())
     }
     
+    /// Export per-decoy effectiveness to CSV
+    pub fn export_effectiveness_csv(report: &DeceptionReport, output_path: impl AsRef<Path>) -> Result<(), ReportingError> {
+        use csv::Writer;
+        
+        let effectiveness = report.effectiveness.as_ref()
+            .ok_or_else(|| ReportingError::ReportGenerationFailed("Report has no deception effectiveness data".to_string()))?;
+        let mut wtr = Writer::from_path(output_path)
+            .map_err(|e| ReportingError::IoError(std::io::Error::new(std::io::ErrorKind::Other, format!("Failed to create CSV writer: {}", e))))?;
+        
+        wtr.write_record(&[
+            "Kind",
+            "Asset",
+            "Name",
+            "Deployed At",
+            "Interactions",
+            "First Touch",
+            "Last Touch",
+            "Time To First Touch (s)",
+            "Unique Agents",
+            "Unique Users",
+            "Unique Processes",
+            "Unique Source IPs",
+            "Detections",
+            "Incidents",
+        ])
+        .map_err(|e| ReportingError::ReportGenerationFailed(format!("Failed to write CSV: {}", e)))?;
+        
+        let time = |t: Option<DateTime<Utc>>| t.map(|t| t.to_rfc3339()).unwrap_or_default();
+        for a in &effectiveness.assets {
+            wtr.write_record(&[
+                a.kind.clone(),
+                a.key.clone(),
+                a.name.clone().unwrap_or_default(),
+                time(a.deployed_at),
+                a.interactions.to_string(),
+                time(a.first_touch_at),
+                time(a.last_touch_at),
+                a.time_to_first_touch_secs.map(|s| s.to_string()).unwrap_or_default(),
+                a.unique_agents.to_string(),
+                a.unique_users.to_string(),
+                a.unique_processes.to_string(),
+                a.unique_source_ips.to_string(),
+                a.detections.to_string(),
+                a.incidents.to_string(),
+            ])
+            .map_err(|e| ReportingError::ReportGenerationFailed(format!("Failed to write CSV: {}", e)))?;
+        }
+        
+        wtr.flush()
+            .map_err(|e| ReportingError::ReportGenerationFailed(format!("Failed to flush CSV: {}", e)))?;
+        
+        info!("Exported deception effectiveness to CSV");
+        Ok(())
+    }
+    
     /// Export report to HTML
     pub fn export_html(report: &DeceptionReport, output_path: impl AsRef<Path>) -> Result<(), ReportingError> {
         let html = format!(
@@ -329,6 +439,8 @@ impl DeceptionReportBuilder {
         {}
     </table>
     
+    {}
+    
     <div class="footer">
         <p>© RansomEye.Tech | Support: Gagan@RansomEye.Tech</p>
         <p>Generated: {}</p>
@@ -371,6 +483,7 @@ impl DeceptionReportBuilder {
                     s.event_count
                 )
             }).collect::<Vec<_>>().join("\n        "),
+            Self::effectiveness_html(report.effectiveness.as_ref()),
             Utc::now().to_rfc3339()
         );
         
@@ -382,6 +495,53 @@ impl DeceptionReportBuilder {
     }
 }
 
+impl DeceptionReportBuilder {
+    /// Decoy effectiveness table (empty when the report carries no analytics run)
+    fn effectiveness_html(effectiveness: Option<&DeceptionEffectiveness>) -> String {
+        let Some(e) = effectiveness else {
+            return String::new();
+        };
+        let rows = e.assets.iter().map(|a| {
+            format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                a.kind,
+                a.name.as_deref().unwrap_or(&a.key),
+                a.interactions,
+                a.time_to_first_touch_secs.map(|s| format!("{}s", s)).unwrap_or_else(|| "-".to_string()),
+                a.unique_agents,
+                a.unique_users,
+                a.detections,
+                a.incidents
+            )
+        }).collect::<Vec<_>>().join("\n        ");
+        format!(
+            r#"<h2>Decoy Effectiveness</h2>
+    <p>{} to {} | {} interactions | {} touched, {} untouched decoys | {} detections | {} incidents</p>
+    <table>
+        <tr>
+            <th>Kind</th>
+            <th>Decoy</th>
+            <th>Interactions</th>
+            <th>Time To First Touch</th>
+            <th>Agents</th>
+            <th>Users</th>
+            <th>Detections</th>
+            <th>Incidents</th>
+        </tr>
+        {}
+    </table>"#,
+            e.window_start.to_rfc3339(),
+            e.generated_at.to_rfc3339(),
+            e.interactions,
+            e.touched_assets,
+            e.untouched_assets,
+            e.detections,
+            e.incidents,
+            rows
+        )
+    }
+}
+
 impl Default for DeceptionReportBuilder {
     fn default() -> Self {
         Self::new()
@@ -0,0 +1,256 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_reporting/src/distribution.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Scheduled report distribution - email (SMTP, artifacts attached) and signed webhook delivery, each message carrying the SHA-256 of every artifact and the preserving evidence bundle
+
+#![cfg(feature = "future-reporting")]
+
+/*
+ * Report Distribution
+ *
+ * Every recipient receives the same integrity material: the SHA-256 of each exported artifact and
+ * the id and hash of the evidence bundle that preserves the report record.
+ *
+ * Email: one message per report to all configured recipients, artifacts attached. The body lists
+ * the hashes in sha256sum format ("<hash>  <file>"), so saving it next to the attachments and
+ * running `sha256sum -c` verifies them. With downloads configured the artifacts are not attached;
+ * the body carries one expiring, use-limited download link per artifact (download_token.rs).
+ * Subject and body come from the notification templates (notification_templates.rs) of the
+ * report's tenant and locale.
+ *
+ * Webhook: POST of a "report.generated" JSON notice (GeneratedReport, no artifact bodies) signed
+ * like the ingest lifecycle webhooks (docs/WEBHOOKS.md):
+ *   X-RansomEye-Event:     report.generated
+ *   X-RansomEye-Delivery:  report_id
+ *   X-RansomEye-Signature: t=<unix seconds>,v1=<hex HMAC-SHA256(secret, "<t>.<body>")>
+ * The secret is read from the environment variable named by secret_env, never from the config
+ * file. Receivers fetch artifacts out of band and check them against the hashes.
+ */
+
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::net::IpAddr;
+use std::time::Duration;
+use tracing::debug;
+
+use crate::notification_templates::NotificationTemplates;
+use crate::scheduler::{GeneratedReport, ScheduledReportConfig};
+
+pub const REPORT_GENERATED: &str = "report.generated";
+pub const SIGNATURE_HEADER: &str = "X-RansomEye-Signature";
+pub const EVENT_HEADER: &str = "X-RansomEye-Event";
+pub const DELIVERY_HEADER: &str = "X-RansomEye-Delivery";
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(30);
+const WEBHOOK_ATTEMPTS: u32 = 3;
+
+static BUILTIN_TEMPLATES: Lazy<NotificationTemplates> = Lazy::new(NotificationTemplates::builtin);
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+/// SMTP relay. STARTTLS on `port` unless `implicit_tls` (SMTPS, usually 465).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmtpConfig {
+    pub host: String,
+    #[serde(default = "default_smtp_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub implicit_tls: bool,
+    pub from: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    /// Environment variable holding the SMTP password
+    #[serde(default)]
+    pub password_env: Option<String>,
+}
+
+/// Webhook recipient; the signing secret comes from the environment variable `secret_env`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookTarget {
+    pub url: String,
+    pub secret_env: String,
+}
+
+/// Result of one delivery.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeliveryReceipt {
+    /// "email:<recipients>" or "webhook:<url>"
+    pub target: String,
+    pub delivered: bool,
+    pub error: Option<String>,
+}
+
+/// Webhook request body.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportNotice<'a> {
+    pub event_type: &'static str,
+    pub report: &'a GeneratedReport,
+}
+
+/// HTTPS required; plain HTTP only to loopback receivers (same rule as ingest webhooks).
+pub fn validate_webhook_url(url: &str) -> Result<(), String> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| format!("invalid webhook url '{}': {}", url, e))?;
+    let loopback = match parsed.host() {
+        Some(url::Host::Domain(d)) => d.eq_ignore_ascii_case("localhost"),
+        Some(url::Host::Ipv4(ip)) => IpAddr::V4(ip).is_loopback(),
+        Some(url::Host::Ipv6(ip)) => IpAddr::V6(ip).is_loopback(),
+        None => return Err(format!("webhook url '{}' has no host", url)),
+    };
+    match parsed.scheme() {
+        "https" => Ok(()),
+        "http" if loopback => Ok(()),
+        other => Err(format!("webhook url scheme '{}' not allowed (https required)", other)),
+    }
+}
+
+/// X-RansomEye-Signature value for a body sent at `timestamp` (unix seconds).
+pub fn sign(secret: &[u8], timestamp: i64, body: &[u8]) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret);
+    let mut ctx = hmac::Context::with_key(&key);
+    ctx.update(timestamp.to_string().as_bytes());
+    ctx.update(b".");
+    ctx.update(body);
+    format!("t={},v1={}", timestamp, hex::encode(ctx.sign().as_ref()))
+}
+
+/// Subject line of the report email (built-in English template).
+pub fn email_subject(report: &GeneratedReport) -> String {
+    BUILTIN_TEMPLATES.report_subject(report, None, None)
+}
+
+/// Plain-text email body with the integrity hashes (built-in English template).
+pub fn email_body(report: &GeneratedReport) -> String {
+    BUILTIN_TEMPLATES.report_body(report, None, None)
+}
+
+/// Send a report to every recipient configured for its schedule. Never fails as a whole; each
+/// recipient gets a receipt.
+pub fn distribute(
+    report: &GeneratedReport,
+    config: &ScheduledReportConfig,
+    smtp: Option<&SmtpConfig>,
+    templates: &NotificationTemplates,
+) -> Vec<DeliveryReceipt> {
+    let mut receipts = Vec::new();
+    if !config.email.is_empty() {
+        let result = match smtp {
+            Some(smtp) => send_email(report, config, smtp, templates),
+            None => Err("no smtp configuration".to_string()),
+        };
+        receipts.push(receipt(format!("email:{}", config.email.join(",")), result));
+    }
+    for target in &config.webhooks {
+        receipts.push(receipt(format!("webhook:{}", target.url), send_webhook(report, target)));
+    }
+    receipts
+}
+
+fn receipt(target: String, result: Result<(), String>) -> DeliveryReceipt {
+    match result {
+        Ok(()) => DeliveryReceipt { target, delivered: true, error: None },
+        Err(e) => DeliveryReceipt { target, delivered: false, error: Some(e) },
+    }
+}
+
+/// MIME type of an exported artifact, by extension.
+pub fn mime_type(file_name: &str) -> &'static str {
+    match file_name.rsplit('.').next() {
+        Some("pdf") => "application/pdf",
+        Some("html") => "text/html; charset=utf-8",
+        Some("csv") => "text/csv; charset=utf-8",
+        Some("json") => "application/json",
+        _ => "application/octet-stream",
+    }
+}
+
+fn content_type(file_name: &str) -> ContentType {
+    ContentType::parse(mime_type(file_name)).unwrap_or(ContentType::TEXT_PLAIN)
+}
+
+fn send_email(
+    report: &GeneratedReport,
+    config: &ScheduledReportConfig,
+    smtp: &SmtpConfig,
+    templates: &NotificationTemplates,
+) -> Result<(), String> {
+    let (tenant, locale) = (config.tenant.as_deref(), config.locale.as_deref());
+    let from: Mailbox = smtp.from.parse().map_err(|e| format!("invalid from address '{}': {}", smtp.from, e))?;
+    let mut builder = Message::builder().from(from).subject(templates.report_subject(report, tenant, locale));
+    for to in &config.email {
+        let mailbox: Mailbox = to.parse().map_err(|e| format!("invalid recipient '{}': {}", to, e))?;
+        builder = builder.to(mailbox);
+    }
+
+    let mut parts = MultiPart::mixed().singlepart(SinglePart::plain(templates.report_body(report, tenant, locale)));
+    // Download links replace attachments
+    let attached = if report.downloads.is_empty() { report.artifacts.as_slice() } else { &[] };
+    for artifact in attached {
+        let data = fs::read(report.directory.join(&artifact.file_name))
+            .map_err(|e| format!("failed to read artifact {}: {}", artifact.file_name, e))?;
+        parts = parts.singlepart(Attachment::new(artifact.file_name.clone()).body(data, content_type(&artifact.file_name)));
+    }
+    let message = builder.multipart(parts).map_err(|e| format!("failed to build email: {}", e))?;
+
+    let relay = if smtp.implicit_tls {
+        SmtpTransport::relay(&smtp.host)
+    } else {
+        SmtpTransport::starttls_relay(&smtp.host)
+    };
+    let mut transport = relay.map_err(|e| format!("smtp relay {}: {}", smtp.host, e))?.port(smtp.port);
+    if let Some(username) = &smtp.username {
+        let password = match &smtp.password_env {
+            Some(var) => std::env::var(var).map_err(|_| format!("smtp password variable {} is not set", var))?,
+            None => String::new(),
+        };
+        transport = transport.credentials(Credentials::new(username.clone(), password));
+    }
+    transport
+        .build()
+        .send(&message)
+        .map_err(|e| format!("smtp send failed: {}", e))?;
+    debug!("Report {} emailed to {} recipient(s)", report.report_id, config.email.len());
+    Ok(())
+}
+
+fn send_webhook(report: &GeneratedReport, target: &WebhookTarget) -> Result<(), String> {
+    let secret = std::env::var(&target.secret_env)
+        .map_err(|_| format!("webhook secret variable {} is not set", target.secret_env))?;
+    let body = serde_json::to_vec(&ReportNotice { event_type: REPORT_GENERATED, report })
+        .map_err(|e| format!("notice serialization: {}", e))?;
+    let client = reqwest::blocking::Client::builder()
+        .timeout(WEBHOOK_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .map_err(|e| format!("failed to build webhook HTTP client: {}", e))?;
+
+    let mut last_error = String::new();
+    for attempt in 1..=WEBHOOK_ATTEMPTS {
+        // Re-sign per attempt so the timestamp stays inside receivers' skew window
+        let signature = sign(secret.as_bytes(), Utc::now().timestamp(), &body);
+        let result = client
+            .post(&target.url)
+            .header("Content-Type", "application/json")
+            .header(EVENT_HEADER, REPORT_GENERATED)
+            .header(DELIVERY_HEADER, &report.report_id)
+            .header(SIGNATURE_HEADER, signature)
+            .body(body.clone())
+            .send();
+        match result {
+            Ok(response) if response.status().is_success() => return Ok(()),
+            Ok(response) => last_error = format!("HTTP {}", response.status().as_u16()),
+            Err(e) => last_error = format!("transport error: {}", e),
+        }
+        if attempt < WEBHOOK_ATTEMPTS {
+            std::thread::sleep(Duration::from_secs(2u64.pow(attempt)));
+        }
+    }
+    Err(format!("{} after {} attempts", last_error, WEBHOOK_ATTEMPTS))
+}
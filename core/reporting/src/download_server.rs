@@ -0,0 +1,110 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_reporting/src/download_server.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: HTTP endpoint serving scheduled report artifacts to holders of a valid download token
+
+#![cfg(feature = "future-reporting")]
+
+/*
+ * Report Download Server
+ *
+ *   GET /reports/download/<token>
+ *     200  artifact body; Content-Disposition attachment, X-Content-SHA256, Cache-Control no-store
+ *     403  forged, expired or used-up token
+ *     404  artifact no longer in the output directory
+ *     500  artifact altered since generation, or the custody record could not be written
+ *
+ * Redemption (download_token.rs) is synchronous and serialized: the use ledger and the audit log
+ * are single files. The peer address is recorded as the requester; bind to loopback behind the
+ * TLS-terminating proxy that base_url points at.
+ */
+
+use axum::extract::{ConnectInfo, Path as UrlPath, State};
+use axum::http::{header, HeaderName, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use chrono::Utc;
+use parking_lot::Mutex;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+use crate::distribution::mime_type;
+use crate::download_token::{DownloadService, DOWNLOAD_PATH};
+use crate::errors::ReportingError;
+
+pub const SHA256_HEADER: &str = "x-content-sha256";
+
+pub type SharedDownloadService = Arc<Mutex<DownloadService>>;
+
+pub fn router(service: SharedDownloadService) -> Router {
+    Router::new()
+        .route(&format!("{}/:token", DOWNLOAD_PATH), get(handle_download))
+        .with_state(service)
+}
+
+pub async fn serve(listen: SocketAddr, service: DownloadService) -> Result<(), ReportingError> {
+    let listener = tokio::net::TcpListener::bind(listen).await?;
+    info!("Report download server listening on {}", listen);
+    let app = router(Arc::new(Mutex::new(service)));
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
+    Ok(())
+}
+
+/// HTTP status for a refused redemption.
+pub fn status_for(e: &ReportingError) -> StatusCode {
+    match e {
+        ReportingError::DownloadDenied(_) => StatusCode::FORBIDDEN,
+        ReportingError::MissingEvidence(_) => StatusCode::NOT_FOUND,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+async fn handle_download(
+    State(service): State<SharedDownloadService>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    UrlPath(token): UrlPath<String>,
+) -> Response {
+    let requester = peer.to_string();
+    let redeemed = tokio::task::spawn_blocking(move || service.lock().redeem(&token, &requester, Utc::now())).await;
+    let download = match redeemed {
+        Ok(Ok(download)) => download,
+        Ok(Err(e)) => {
+            let status = status_for(&e);
+            if status == StatusCode::INTERNAL_SERVER_ERROR {
+                error!("FAIL-CLOSED: report download from {} refused: {}", peer, e);
+            } else {
+                warn!("Report download from {} refused: {}", peer, e);
+            }
+            return status.into_response();
+        }
+        Err(e) => {
+            error!("FAIL-CLOSED: report download task failed: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    info!(
+        "Report artifact {}/{} downloaded by {} (use {} of {}, custody record {})",
+        download.claims.report_id,
+        download.claims.file_name,
+        peer,
+        download.use_number,
+        download.claims.max_uses,
+        download.audit_record_id
+    );
+    let disposition = format!("attachment; filename=\"{}\"", download.claims.file_name);
+    let headers = [
+        (header::CONTENT_TYPE, HeaderValue::from_static(mime_type(&download.claims.file_name))),
+        (header::CACHE_CONTROL, HeaderValue::from_static("no-store")),
+        (
+            header::CONTENT_DISPOSITION,
+            HeaderValue::from_str(&disposition).unwrap_or_else(|_| HeaderValue::from_static("attachment")),
+        ),
+        (
+            HeaderName::from_static(SHA256_HEADER),
+            HeaderValue::from_str(&download.claims.sha256).unwrap_or_else(|_| HeaderValue::from_static("")),
+        ),
+    ];
+    (StatusCode::OK, headers, download.data).into_response()
+}
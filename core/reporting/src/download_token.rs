@@ -0,0 +1,365 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_reporting/src/download_token.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Signed, expiring, use-limited download tokens for scheduled report artifacts, with every download recorded in the signed chain-of-custody audit log
+
+#![cfg(feature = "future-reporting")]
+
+/*
+ * Report Download Tokens
+ *
+ * Report artifacts can contain sensitive forensic data, so with a `downloads` section the
+ * scheduler no longer attaches them to email: recipients get one link per artifact instead.
+ * A link carries a token
+ *
+ *   <base64url(claims JSON)>.<base64url(HMAC-SHA256(secret, claims JSON))>
+ *
+ * whose claims name one artifact (schedule, report_id, file_name), its SHA-256, an expiry and a
+ * maximum number of uses. The secret is read from the environment variable named by secret_env.
+ *
+ * Redeeming a token (GET /reports/download/<token>, see download_server.rs):
+ *   1. the signature is checked; forged or altered tokens are refused without an audit record,
+ *   2. expiry and the use count in <output_dir>/download_tokens.json are checked,
+ *   3. the artifact is read and its SHA-256 compared with the token's,
+ *   4. the use is recorded in download_tokens.json, then a REPORT_DOWNLOADED record goes to the
+ *      signed, hash-chained audit log (the chain of custody). If either write fails nothing is
+ *      served (AuditFailed for the custody record, a server-side error, not a refused token); a
+ *      use consumed without a custody record is lost, never the other way round.
+ * Refusals of authentic tokens (expired, used up, missing or altered artifact) are audited as
+ * REPORT_DOWNLOAD_DENIED.
+ */
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use audit::AuditLogger;
+
+use crate::errors::ReportingError;
+use crate::hasher::EvidenceHasher;
+use crate::scheduler::{GeneratedReport, ReportArtifact};
+
+pub const AUDIT_COMPONENT: &str = "reporting.downloads";
+pub const REPORT_DOWNLOADED: &str = "REPORT_DOWNLOADED";
+pub const REPORT_DOWNLOAD_DENIED: &str = "REPORT_DOWNLOAD_DENIED";
+pub const DOWNLOAD_PATH: &str = "/reports/download";
+
+const LEDGER_FILE: &str = "download_tokens.json";
+const MIN_SECRET_BYTES: usize = 32;
+/// Links are for handing a report over, not for archiving: at most 30 days
+const MAX_TTL_SECS: u64 = 30 * 86_400;
+
+fn default_secret_env() -> String {
+    "RANSOMEYE_REPORT_DOWNLOAD_SECRET".to_string()
+}
+
+fn default_ttl_secs() -> u64 {
+    86_400
+}
+
+fn default_max_uses() -> u32 {
+    1
+}
+
+/// `downloads` section of the scheduler configuration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadConfig {
+    /// Public base URL of the download server (https, or http to loopback)
+    pub base_url: String,
+    /// Environment variable holding the token signing secret (at least 32 bytes)
+    #[serde(default = "default_secret_env")]
+    pub secret_env: String,
+    #[serde(default = "default_ttl_secs")]
+    pub ttl_secs: u64,
+    /// Downloads allowed per token
+    #[serde(default = "default_max_uses")]
+    pub max_uses: u32,
+}
+
+impl DownloadConfig {
+    pub fn validate(&self) -> Result<(), ReportingError> {
+        crate::distribution::validate_webhook_url(&self.base_url)
+            .map_err(|e| ReportingError::InvalidConfiguration(format!("downloads.base_url: {}", e)))?;
+        if self.ttl_secs == 0 || self.ttl_secs > MAX_TTL_SECS {
+            return Err(ReportingError::InvalidConfiguration(format!("downloads.ttl_secs must be 1..={}", MAX_TTL_SECS)));
+        }
+        if self.max_uses == 0 {
+            return Err(ReportingError::InvalidConfiguration("downloads.max_uses must be > 0".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// What a token grants.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadClaims {
+    pub token_id: String,
+    pub schedule: String,
+    pub report_id: String,
+    pub file_name: String,
+    pub sha256: String,
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub max_uses: u32,
+}
+
+impl DownloadClaims {
+    pub fn artifact_path(&self, output_dir: &Path) -> Result<PathBuf, ReportingError> {
+        artifact_path(output_dir, &self.schedule, &self.report_id, &self.file_name)
+    }
+}
+
+/// Artifact location under the scheduler output directory (<schedule>/<report_id>/<file>).
+pub fn artifact_path(output_dir: &Path, schedule: &str, report_id: &str, file_name: &str) -> Result<PathBuf, ReportingError> {
+    for part in [schedule, report_id, file_name] {
+        if part.is_empty() || part.contains(['/', '\\']) || part == ".." || part == "." {
+            return Err(ReportingError::DownloadDenied(format!("invalid artifact path component '{}'", part)));
+        }
+    }
+    Ok(output_dir.join(schedule).join(report_id).join(file_name))
+}
+
+/// Link handed to a recipient for one artifact.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DownloadLink {
+    pub file_name: String,
+    pub url: String,
+    pub expires_at: DateTime<Utc>,
+    pub max_uses: u32,
+}
+
+/// Signs and verifies tokens.
+pub struct DownloadSigner {
+    key: hmac::Key,
+}
+
+impl DownloadSigner {
+    pub fn new(secret: &[u8]) -> Result<Self, ReportingError> {
+        if secret.len() < MIN_SECRET_BYTES {
+            return Err(ReportingError::InvalidConfiguration(format!(
+                "download token secret must be at least {} bytes",
+                MIN_SECRET_BYTES
+            )));
+        }
+        Ok(Self { key: hmac::Key::new(hmac::HMAC_SHA256, secret) })
+    }
+
+    pub fn from_env(var: &str) -> Result<Self, ReportingError> {
+        let secret = std::env::var(var)
+            .map_err(|_| ReportingError::InvalidConfiguration(format!("download token secret variable {} is not set", var)))?;
+        Self::new(secret.as_bytes())
+    }
+
+    pub fn sign(&self, claims: &DownloadClaims) -> Result<String, ReportingError> {
+        let body = serde_json::to_vec(claims)?;
+        let tag = hmac::sign(&self.key, &body);
+        Ok(format!("{}.{}", URL_SAFE_NO_PAD.encode(&body), URL_SAFE_NO_PAD.encode(tag.as_ref())))
+    }
+
+    /// Claims of an authentic token (expiry and uses are checked on redemption).
+    pub fn verify(&self, token: &str) -> Result<DownloadClaims, ReportingError> {
+        let denied = |reason: &str| ReportingError::DownloadDenied(reason.to_string());
+        let (body, tag) = token.split_once('.').ok_or_else(|| denied("malformed token"))?;
+        let body = URL_SAFE_NO_PAD.decode(body).map_err(|_| denied("malformed token"))?;
+        let tag = URL_SAFE_NO_PAD.decode(tag).map_err(|_| denied("malformed token"))?;
+        hmac::verify(&self.key, &body, &tag).map_err(|_| denied("invalid token signature"))?;
+        serde_json::from_slice(&body).map_err(|_| denied("malformed token claims"))
+    }
+
+    /// Token for one artifact of a report.
+    pub fn issue(
+        &self,
+        schedule: &str,
+        report_id: &str,
+        artifact: &ReportArtifact,
+        ttl: ChronoDuration,
+        max_uses: u32,
+        now: DateTime<Utc>,
+    ) -> Result<(DownloadClaims, String), ReportingError> {
+        let claims = DownloadClaims {
+            token_id: uuid::Uuid::new_v4().to_string(),
+            schedule: schedule.to_string(),
+            report_id: report_id.to_string(),
+            file_name: artifact.file_name.clone(),
+            sha256: artifact.sha256.clone(),
+            issued_at: now,
+            expires_at: now + ttl,
+            max_uses,
+        };
+        let token = self.sign(&claims)?;
+        Ok((claims, token))
+    }
+
+    /// Link to one artifact, with the configured lifetime and use count.
+    pub fn link(
+        &self,
+        config: &DownloadConfig,
+        schedule: &str,
+        report_id: &str,
+        artifact: &ReportArtifact,
+        now: DateTime<Utc>,
+    ) -> Result<DownloadLink, ReportingError> {
+        let ttl = ChronoDuration::seconds(config.ttl_secs as i64);
+        let (claims, token) = self.issue(schedule, report_id, artifact, ttl, config.max_uses, now)?;
+        Ok(DownloadLink {
+            file_name: claims.file_name,
+            url: format!("{}{}/{}", config.base_url.trim_end_matches('/'), DOWNLOAD_PATH, token),
+            expires_at: claims.expires_at,
+            max_uses: claims.max_uses,
+        })
+    }
+
+    /// One link per artifact of `report`.
+    pub fn links(&self, report: &GeneratedReport, config: &DownloadConfig, now: DateTime<Utc>) -> Result<Vec<DownloadLink>, ReportingError> {
+        report
+            .artifacts
+            .iter()
+            .map(|artifact| self.link(config, &report.schedule, &report.report_id, artifact, now))
+            .collect()
+    }
+}
+
+/// Uses of one token, as recorded in download_tokens.json.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenUses {
+    pub uses: u32,
+    pub expires_at: DateTime<Utc>,
+    pub last_download_at: DateTime<Utc>,
+}
+
+/// <output_dir>/download_tokens.json - uses per token id; entries are dropped once expired.
+pub struct DownloadLedger {
+    path: PathBuf,
+    tokens: BTreeMap<String, TokenUses>,
+}
+
+impl DownloadLedger {
+    pub fn open(output_dir: &Path) -> Result<Self, ReportingError> {
+        let path = output_dir.join(LEDGER_FILE);
+        let tokens = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(Self { path, tokens })
+    }
+
+    pub fn uses(&self, token_id: &str) -> u32 {
+        self.tokens.get(token_id).map(|t| t.uses).unwrap_or(0)
+    }
+
+    pub fn record_use(&mut self, claims: &DownloadClaims, now: DateTime<Utc>) -> Result<u32, ReportingError> {
+        let mut tokens = self.tokens.clone();
+        tokens.retain(|_, t| t.expires_at > now);
+        let entry = tokens
+            .entry(claims.token_id.clone())
+            .or_insert(TokenUses { uses: 0, expires_at: claims.expires_at, last_download_at: now });
+        entry.uses += 1;
+        entry.last_download_at = now;
+        let uses = entry.uses;
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(&tokens)?)?;
+        fs::rename(&tmp, &self.path)?;
+        self.tokens = tokens;
+        Ok(uses)
+    }
+}
+
+/// A served artifact.
+#[derive(Debug, Clone)]
+pub struct Download {
+    pub claims: DownloadClaims,
+    pub data: Vec<u8>,
+    /// Use number of this download (1-based)
+    pub use_number: u32,
+    pub audit_record_id: String,
+}
+
+/// Token redemption against the scheduler output directory, with custody records.
+pub struct DownloadService {
+    signer: DownloadSigner,
+    ledger: DownloadLedger,
+    output_dir: PathBuf,
+    audit: AuditLogger,
+    hasher: EvidenceHasher,
+}
+
+impl DownloadService {
+    pub fn new(signer: DownloadSigner, output_dir: impl AsRef<Path>, audit: AuditLogger) -> Result<Self, ReportingError> {
+        let output_dir = output_dir.as_ref().to_path_buf();
+        Ok(Self { signer, ledger: DownloadLedger::open(&output_dir)?, output_dir, audit, hasher: EvidenceHasher::new() })
+    }
+
+    /// Check a token and return its artifact. `requester` (the peer address) is recorded in the
+    /// custody log.
+    pub fn redeem(&mut self, token: &str, requester: &str, now: DateTime<Utc>) -> Result<Download, ReportingError> {
+        let claims = self.signer.verify(token)?;
+        match self.check(&claims, now) {
+            Ok(data) => self.serve(claims, data, requester, now),
+            Err(e) => {
+                self.custody(&claims, REPORT_DOWNLOAD_DENIED, requester, serde_json::json!({ "reason": e.to_string() }))?;
+                Err(e)
+            }
+        }
+    }
+
+    fn check(&self, claims: &DownloadClaims, now: DateTime<Utc>) -> Result<Vec<u8>, ReportingError> {
+        if now >= claims.expires_at {
+            return Err(ReportingError::DownloadDenied(format!("token expired at {}", claims.expires_at.to_rfc3339())));
+        }
+        let used = self.ledger.uses(&claims.token_id);
+        if used >= claims.max_uses {
+            return Err(ReportingError::DownloadDenied(format!("token used {} of {} time(s)", used, claims.max_uses)));
+        }
+        let path = claims.artifact_path(&self.output_dir)?;
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(ReportingError::MissingEvidence(format!("report artifact {}", path.display())));
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let actual = self.hasher.hash_bytes(&data);
+        if actual != claims.sha256 {
+            return Err(ReportingError::HashMismatch { expected: claims.sha256.clone(), actual });
+        }
+        Ok(data)
+    }
+
+    fn serve(&mut self, claims: DownloadClaims, data: Vec<u8>, requester: &str, now: DateTime<Utc>) -> Result<Download, ReportingError> {
+        let use_number = self.ledger.record_use(&claims, now)?;
+        let audit_record_id = self.custody(
+            &claims,
+            REPORT_DOWNLOADED,
+            requester,
+            serde_json::json!({ "use": use_number, "bytes": data.len() }),
+        )?;
+        Ok(Download { claims, data, use_number, audit_record_id })
+    }
+
+    fn custody(
+        &mut self,
+        claims: &DownloadClaims,
+        event_type: &str,
+        requester: &str,
+        mut details: serde_json::Value,
+    ) -> Result<String, ReportingError> {
+        details["token_id"] = claims.token_id.clone().into();
+        details["schedule"] = claims.schedule.clone().into();
+        details["report_id"] = claims.report_id.clone().into();
+        details["file_name"] = claims.file_name.clone().into();
+        details["sha256"] = claims.sha256.clone().into();
+        details["max_uses"] = claims.max_uses.into();
+        details["expires_at"] = claims.expires_at.to_rfc3339().into();
+        self.audit
+            .log(AUDIT_COMPONENT, event_type, requester, &crate::worm::hostname(), details)
+            .map_err(|e| ReportingError::AuditFailed(format!("chain-of-custody record failed: {}", e)))
+    }
+}
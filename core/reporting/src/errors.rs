@@ -47,5 +47,20 @@ pub enum ReportingError {
     
     #[error("Evidence store locked: {0}")]
     StoreLocked(String),
+    
+    #[error("Invalid report schedule: {0}")]
+    InvalidSchedule(String),
+    
+    #[error("WORM violation: {0}")]
+    WormViolation(String),
+    
+    #[error("Invalid configuration: {0}")]
+    InvalidConfiguration(String),
+    
+    #[error("Download denied: {0}")]
+    DownloadDenied(String),
+    
+    #[error("Audit record failed: {0}")]
+    AuditFailed(String),
 }
 
@@ -1,6 +1,6 @@
 // Path and File Name : /home/ransomeye/rebuild/ransomeye_reporting/src/evidence_store.rs
 // Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
-// Details of functionality of this file: Immutable evidence store - provides append-only storage with hash chaining and cryptographic signatures
+// Details of functionality of this file: Immutable evidence store - provides append-only storage with hash chaining and cryptographic signatures; evidence payloads are deduplicated and compressed in the blob store
 
 #![cfg(feature = "future-reporting")]
 
@@ -10,7 +10,7 @@ use serde_json::Value;
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use tracing::{debug, error, warn};
 use ring::signature::{self, Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519};
 use ring::rand::SystemRandom;
@@ -19,6 +19,13 @@ use base64::{Engine as _, engine::general_purpose};
 use crate::errors::ReportingError;
 use crate::hasher::EvidenceHasher;
 use crate::collector::CollectedEvidence;
+use crate::blob_store::{BlobStore, StorageStats, DEFAULT_COMPRESSION_THRESHOLD};
+use crate::worm::{self, ReleaseLedger, ReleaseRecord, ReleaseRequest};
+use audit::AuditLogger;
+
+/// On-disk evidence items carry their payload as a blob reference under this key instead of
+/// inline `data` (bundles written before deduplication keep inline `data` and still load).
+const DATA_BLOB_KEY: &str = "data_blob";
 
 /// Evidence bundle - sealed and immutable once created
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,6 +42,41 @@ pub struct EvidenceBundle {
     pub is_sealed: bool,
 }
 
+/// Evidence store options
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EvidenceStoreOptions {
+    /// Payloads of at least this many bytes are stored compressed
+    pub compression_threshold: usize,
+    /// Set the filesystem immutable flag on finalized files (see worm.rs)
+    pub worm: bool,
+}
+
+impl Default for EvidenceStoreOptions {
+    fn default() -> Self {
+        Self {
+            compression_threshold: DEFAULT_COMPRESSION_THRESHOLD,
+            worm: false,
+        }
+    }
+}
+
+impl EvidenceStoreOptions {
+    /// RANSOMEYE_EVIDENCE_WORM=true|1 (default off),
+    /// RANSOMEYE_EVIDENCE_COMPRESSION_THRESHOLD=<bytes> (default 4096)
+    pub fn from_env() -> Result<Self, ReportingError> {
+        let mut options = Self::default();
+        if let Ok(v) = std::env::var("RANSOMEYE_EVIDENCE_WORM") {
+            options.worm = v == "1" || v.eq_ignore_ascii_case("true");
+        }
+        if let Ok(v) = std::env::var("RANSOMEYE_EVIDENCE_COMPRESSION_THRESHOLD") {
+            options.compression_threshold = v.parse().map_err(|_| ReportingError::InvalidConfiguration(
+                format!("Invalid RANSOMEYE_EVIDENCE_COMPRESSION_THRESHOLD='{}'", v)
+            ))?;
+        }
+        Ok(options)
+    }
+}
+
 /// Immutable evidence store with hash chaining
 pub struct EvidenceStore {
     store_path: PathBuf,
@@ -42,12 +84,25 @@ pub struct EvidenceStore {
     key_pair: Option<Ed25519KeyPair>,
     bundles: RwLock<Vec<EvidenceBundle>>,
     last_bundle_hash: RwLock<Option<String>>,
+    blobs: BlobStore,
+    worm: bool,
+    released: Mutex<ReleaseLedger>,
 }
 
 impl EvidenceStore {
     /// Create new evidence store
     /// If signing_key_path is provided, bundles will be cryptographically signed
     pub fn new(store_path: impl AsRef<Path>, signing_key_path: Option<&Path>) -> Result<Self, ReportingError> {
+        Self::open(store_path, signing_key_path, EvidenceStoreOptions::default())
+    }
+    
+    /// Create evidence store with explicit options
+    /// With WORM enabled, fails closed if immutable files are not supported or not permitted
+    pub fn open(
+        store_path: impl AsRef<Path>,
+        signing_key_path: Option<&Path>,
+        options: EvidenceStoreOptions,
+    ) -> Result<Self, ReportingError> {
         let store_path = store_path.as_ref().to_path_buf();
         
         // Create store directory if it doesn't exist
@@ -78,17 +133,35 @@ impl EvidenceStore {
             None
         };
         
+        if options.worm {
+            worm::probe(&store_path.join("worm"))?;
+        }
+        let blobs = BlobStore::open(store_path.join("blobs"), options.compression_threshold)?;
+        let released = ReleaseLedger::open(&store_path)?;
+        
         let store = Self {
             store_path,
             hasher: EvidenceHasher::new(),
             key_pair,
             bundles: RwLock::new(Vec::new()),
             last_bundle_hash: RwLock::new(None),
+            blobs,
+            worm: options.worm,
+            released: Mutex::new(released),
         };
         
         // Load existing bundles
         store.load_bundles()?;
         
+        // Bring bundles sealed before WORM was enabled under protection
+        if store.worm {
+            for bundle in store.bundles.read().iter().filter(|b| b.is_sealed) {
+                if !store.released.lock().is_released(&bundle.bundle_id) {
+                    store.lock_bundle(bundle)?;
+                }
+            }
+        }
+        
         Ok(store)
     }
     
@@ -112,8 +185,9 @@ impl EvidenceStore {
         for bundle_file in bundle_files {
             let bundle_data = fs::read_to_string(&bundle_file)
                 .map_err(|e| ReportingError::IoError(e))?;
-            let bundle: EvidenceBundle = serde_json::from_str(&bundle_data)
+            let stored: Value = serde_json::from_str(&bundle_data)
                 .map_err(|e| ReportingError::SerializationError(e))?;
+            let bundle = self.rehydrate(stored)?;
             
             // Verify bundle integrity
             if !self.verify_bundle_integrity(&bundle)? {
@@ -202,13 +276,11 @@ impl EvidenceStore {
             return Err(ReportingError::BundleSealed(format!("Bundle {} is already sealed", bundle_id)));
         }
         
+        bundle.sealed_at = Some(Utc::now());
+        bundle.is_sealed = true;
+        
         // Compute bundle hash
-        let bundle_hash = {
-            let bundle_value = serde_json::to_value(&*bundle)
-                .map_err(|e| ReportingError::SerializationError(e))?;
-            self.hasher.hash_evidence(&bundle_value)
-        };
-        bundle.bundle_hash = bundle_hash;
+        bundle.bundle_hash = self.content_hash(bundle)?;
         
         // Sign bundle if key pair is available
         if let Some(key_pair) = &self.key_pair {
@@ -217,11 +289,11 @@ impl EvidenceStore {
             bundle.signature = Some(general_purpose::STANDARD.encode(signature.as_ref()));
         }
         
-        bundle.sealed_at = Some(Utc::now());
-        bundle.is_sealed = true;
-        
         // Save bundle to disk
         self.save_bundle(bundle)?;
+        if self.worm {
+            self.lock_bundle(bundle)?;
+        }
         
         // Update last bundle hash
         *self.last_bundle_hash.write() = Some(bundle.bundle_hash.clone());
@@ -230,14 +302,28 @@ impl EvidenceStore {
         Ok(())
     }
     
+    /// Hash over the sealed bundle with its own hash and signature blanked
+    /// (the state at sealing time, so verification recomputes the same value)
+    fn content_hash(&self, bundle: &EvidenceBundle) -> Result<String, ReportingError> {
+        let mut bundle_value = serde_json::to_value(bundle)
+            .map_err(|e| ReportingError::SerializationError(e))?;
+        if let Some(obj) = bundle_value.as_object_mut() {
+            obj.insert("bundle_hash".to_string(), Value::String(String::new()));
+            obj.insert("signature".to_string(), Value::Null);
+        }
+        Ok(self.hasher.hash_evidence(&bundle_value))
+    }
+    
     /// Save bundle to disk
+    /// Evidence payloads go to the blob store; the bundle file keeps their SHA-256 references
     fn save_bundle(&self, bundle: &EvidenceBundle) -> Result<(), ReportingError> {
         let bundles_dir = self.store_path.join("bundles");
         fs::create_dir_all(&bundles_dir)
             .map_err(|e| ReportingError::IoError(e))?;
         
         let bundle_file = bundles_dir.join(format!("{}.json", bundle.bundle_id));
-        let bundle_json = serde_json::to_string_pretty(bundle)
+        let stored = self.dehydrate(bundle)?;
+        let bundle_json = serde_json::to_string_pretty(&stored)
             .map_err(|e| ReportingError::SerializationError(e))?;
         
         fs::write(&bundle_file, bundle_json)
@@ -246,12 +332,67 @@ impl EvidenceStore {
         Ok(())
     }
     
+    /// Replace each evidence payload with a blob reference
+    fn dehydrate(&self, bundle: &EvidenceBundle) -> Result<Value, ReportingError> {
+        let mut stored = serde_json::to_value(bundle)
+            .map_err(|e| ReportingError::SerializationError(e))?;
+        if let Some(items) = stored.get_mut("evidence_items").and_then(Value::as_array_mut) {
+            for item in items {
+                let Some(obj) = item.as_object_mut() else { continue };
+                let data = obj.remove("data").unwrap_or(Value::Null);
+                let bytes = serde_json::to_vec(&data)
+                    .map_err(|e| ReportingError::SerializationError(e))?;
+                let digest = self.blobs.put(&bytes)?;
+                obj.insert(DATA_BLOB_KEY.to_string(), Value::String(digest));
+            }
+        }
+        Ok(stored)
+    }
+    
+    /// Restore evidence payloads from blob references (verifying each blob) and count the references
+    fn rehydrate(&self, mut stored: Value) -> Result<EvidenceBundle, ReportingError> {
+        if let Some(items) = stored.get_mut("evidence_items").and_then(Value::as_array_mut) {
+            for item in items {
+                let Some(obj) = item.as_object_mut() else { continue };
+                let Some(reference) = obj.remove(DATA_BLOB_KEY) else { continue };
+                let digest = reference.as_str().ok_or_else(|| {
+                    ReportingError::EvidenceCorrupted(format!("Invalid blob reference {}", reference))
+                })?;
+                let data: Value = serde_json::from_slice(&self.blobs.get(digest)?)
+                    .map_err(|e| ReportingError::SerializationError(e))?;
+                self.blobs.retain(digest);
+                obj.insert("data".to_string(), data);
+            }
+        }
+        let bundle: EvidenceBundle = serde_json::from_value(stored)
+            .map_err(|e| ReportingError::SerializationError(e))?;
+        // Chunks are only checked for presence here; read_chunk verifies their content
+        for digest in bundle.evidence_items.iter().flat_map(|e| &e.chunks) {
+            if self.blobs.locate(digest).is_none() {
+                return Err(ReportingError::EvidenceCorrupted(
+                    format!("Bundle {} references missing chunk {}", bundle.bundle_id, digest)
+                ));
+            }
+            self.blobs.retain(digest);
+        }
+        Ok(bundle)
+    }
+    
+    /// Store one raw payload chunk for an evidence item's `chunks` list. Returns its SHA-256.
+    /// The reference taken here belongs to the evidence item that lists the digest.
+    pub fn put_chunk(&self, data: &[u8]) -> Result<String, ReportingError> {
+        self.blobs.put(data)
+    }
+    
+    /// Read a payload chunk, verified against its digest
+    pub fn read_chunk(&self, digest: &str) -> Result<Vec<u8>, ReportingError> {
+        self.blobs.get(digest)
+    }
+    
     /// Verify bundle integrity
     pub fn verify_bundle_integrity(&self, bundle: &EvidenceBundle) -> Result<bool, ReportingError> {
         // Recompute hash
-        let bundle_value = serde_json::to_value(bundle)
-            .map_err(|e| ReportingError::SerializationError(e))?;
-        let computed_hash = self.hasher.hash_evidence(&bundle_value);
+        let computed_hash = self.content_hash(bundle)?;
         
         if computed_hash != bundle.bundle_hash {
             return Ok(false);
@@ -283,6 +424,119 @@ impl EvidenceStore {
             .ok_or_else(|| ReportingError::MissingEvidence(format!("Bundle {} not found", bundle_id)))
     }
     
+    /// Remove a sealed bundle and release its payload references (retention purge)
+    /// Payloads shared with other bundles stay until their last reference is released
+    /// Under WORM the bundle must have been released first (release_worm)
+    pub fn purge_bundle(&self, bundle_id: &str) -> Result<(), ReportingError> {
+        let mut bundles = self.bundles.write();
+        let index = bundles.iter()
+            .position(|b| b.bundle_id == bundle_id)
+            .ok_or_else(|| ReportingError::MissingEvidence(format!("Bundle {} not found", bundle_id)))?;
+        
+        if bundles[index].is_sealed {
+            if self.worm && !self.released.lock().is_released(bundle_id) {
+                return Err(ReportingError::WormViolation(
+                    format!("Bundle {} is WORM-protected; release it before purging", bundle_id)
+                ));
+            }
+            fs::remove_file(self.bundle_file(bundle_id))
+                .map_err(|e| ReportingError::IoError(e))?;
+            for evidence in &bundles[index].evidence_items {
+                for digest in self.payload_digests(evidence)? {
+                    self.blobs.release(&digest)?;
+                }
+            }
+        }
+        bundles.remove(index);
+        
+        debug!("Purged evidence bundle {}", bundle_id);
+        Ok(())
+    }
+    
+    /// Release a sealed bundle from WORM protection (privileged, two-person, audited)
+    /// The authorization is written to the audit log before any immutable flag is cleared
+    pub fn release_worm(
+        &self,
+        request: &ReleaseRequest,
+        audit: &mut AuditLogger,
+    ) -> Result<ReleaseRecord, ReportingError> {
+        request.validate()?;
+        let bundle = self.get_bundle(&request.bundle_id)?;
+        if !bundle.is_sealed {
+            return Err(ReportingError::WormViolation(format!("Bundle {} is not sealed", bundle.bundle_id)));
+        }
+        if bundle.bundle_hash != request.bundle_hash {
+            return Err(ReportingError::HashMismatch {
+                expected: bundle.bundle_hash,
+                actual: request.bundle_hash.clone(),
+            });
+        }
+        let mut released = self.released.lock();
+        if released.is_released(&bundle.bundle_id) {
+            return Err(ReportingError::WormViolation(format!("Bundle {} is already released", bundle.bundle_id)));
+        }
+        
+        // Payloads another bundle still references stay locked
+        let mut in_bundle: HashMap<String, u64> = HashMap::new();
+        for evidence in &bundle.evidence_items {
+            for digest in self.payload_digests(evidence)? {
+                *in_bundle.entry(digest).or_insert(0) += 1;
+            }
+        }
+        let mut files = vec![self.bundle_file(&bundle.bundle_id)];
+        for (digest, count) in &in_bundle {
+            if self.blobs.references(digest) <= *count {
+                files.extend(self.blobs.locate(digest));
+            }
+        }
+        
+        let record = worm::release(request, &files, &mut released, audit)?;
+        warn!("Bundle {} released from WORM protection by {} (approved by {})",
+              bundle.bundle_id, request.actor, request.approver);
+        Ok(record)
+    }
+    
+    /// Whether the sealed bundle file currently carries the immutable flag
+    pub fn is_worm_locked(&self, bundle_id: &str) -> Result<bool, ReportingError> {
+        worm::is_locked(&self.bundle_file(bundle_id))
+    }
+    
+    /// Set the immutable flag on a sealed bundle's file and payload blobs
+    fn lock_bundle(&self, bundle: &EvidenceBundle) -> Result<(), ReportingError> {
+        worm::lock(&self.bundle_file(&bundle.bundle_id))?;
+        for evidence in &bundle.evidence_items {
+            for digest in self.payload_digests(evidence)? {
+                if let Some(path) = self.blobs.locate(&digest) {
+                    worm::lock(&path)?;
+                }
+            }
+        }
+        Ok(())
+    }
+    
+    fn bundle_file(&self, bundle_id: &str) -> PathBuf {
+        self.store_path.join("bundles").join(format!("{}.json", bundle_id))
+    }
+    
+    /// Blob reference of an evidence payload (as written by dehydrate)
+    fn payload_digest(&self, evidence: &CollectedEvidence) -> Result<String, ReportingError> {
+        let bytes = serde_json::to_vec(&evidence.data)
+            .map_err(|e| ReportingError::SerializationError(e))?;
+        Ok(self.hasher.hash_bytes(&bytes))
+    }
+    
+    /// Every blob an evidence item holds a reference to: its payload, then its chunks
+    fn payload_digests(&self, evidence: &CollectedEvidence) -> Result<Vec<String>, ReportingError> {
+        let mut digests = vec![self.payload_digest(evidence)?];
+        digests.extend(evidence.chunks.iter().cloned());
+        Ok(digests)
+    }
+    
+    /// Payload deduplication and compression savings
+    pub fn storage_stats(&self) -> StorageStats {
+        self.blobs.stats()
+    }
+    
     /// Get all bundles
     pub fn get_all_bundles(&self) -> Vec<EvidenceBundle> {
         self.bundles.read().clone()
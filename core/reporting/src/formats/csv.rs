@@ -45,8 +45,13 @@ pub fn export_csv(
             .map_err(|e| ReportingError::ReportGenerationFailed(format!("Failed to write CSV: {}", e)))?;
     }
     
+    wtr.write_record(&["Redaction Profile", report.metadata.redaction_profile.as_str()])
+        .map_err(|e| ReportingError::ReportGenerationFailed(format!("Failed to write CSV: {}", e)))?;
+    
     wtr.write_record(&["Total Evidence Items", &report.summary.total_evidence_items.to_string()])
         .map_err(|e| ReportingError::ReportGenerationFailed(format!("Failed to write CSV: {}", e)))?;
+    wtr.write_record(&["Data Residency", &report.summary.residency.label()])
+        .map_err(|e| ReportingError::ReportGenerationFailed(format!("Failed to write CSV: {}", e)))?;
     
     // Empty row
     wtr.write_record(&["", ""])
@@ -73,6 +78,30 @@ pub fn export_csv(
         wtr.write_record(&["", hash])
             .map_err(|e| ReportingError::ReportGenerationFailed(format!("Failed to write CSV: {}", e)))?;
     }
+
+    // Annotations: one row per revision, so the edit history is machine-readable
+    if !report.annotations.is_empty() {
+        wtr.write_record(&["", ""])
+            .map_err(|e| ReportingError::ReportGenerationFailed(format!("Failed to write CSV: {}", e)))?;
+        wtr.write_record(&["Annotations", ""])
+            .map_err(|e| ReportingError::ReportGenerationFailed(format!("Failed to write CSV: {}", e)))?;
+        for annotation in &report.annotations {
+            for revision in &annotation.revisions {
+                let label = format!(
+                    "{} {} {} rev {} by {} at {}{}",
+                    annotation.annotation_id,
+                    annotation.subject_type.as_str(),
+                    annotation.subject_id,
+                    revision.revision,
+                    revision.author,
+                    revision.edited_at.to_rfc3339(),
+                    revision.edit_reason.as_deref().map(|r| format!(" ({})", r)).unwrap_or_default()
+                );
+                wtr.write_record(&[label.as_str(), revision.body.as_str()])
+                    .map_err(|e| ReportingError::ReportGenerationFailed(format!("Failed to write CSV: {}", e)))?;
+            }
+        }
+    }
     
     wtr.flush()
         .map_err(|e| ReportingError::ReportGenerationFailed(format!("Failed to flush CSV: {}", e)))?;
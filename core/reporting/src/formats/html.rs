@@ -47,6 +47,7 @@ pub fn export_html(
     if let Some(ref model_hash) = report.metadata.model_version_hash {
         html.push_str(&format!("<tr><td>Model Version Hash:</td><td><code>{}</code></td></tr>\n", escape_html(model_hash)));
     }
+    html.push_str(&format!("<tr><td>Redaction Profile:</td><td>{}</td></tr>\n", report.metadata.redaction_profile.as_str()));
     html.push_str("</table>\n");
     html.push_str("</div>\n");
     
@@ -61,6 +62,7 @@ pub fn export_html(
     html.push_str("<h3>Summary</h3>\n");
     html.push_str("<table>\n");
     html.push_str(&format!("<tr><td>Total Evidence Items:</td><td>{}</td></tr>\n", report.summary.total_evidence_items));
+    html.push_str(&format!("<tr><td>Data Residency:</td><td>{}</td></tr>\n", escape_html(&report.summary.residency.label())));
     if let Some(start) = report.summary.time_range_start {
         html.push_str(&format!("<tr><td>Time Range Start:</td><td>{}</td></tr>\n", start.to_rfc3339()));
     }
@@ -87,6 +89,34 @@ pub fn export_html(
         html.push_str(&format!("<p>{}</p>\n", escape_html(&section.content)));
         html.push_str("</div>\n");
     }
+
+    // Annotation history: every revision of every note, current first
+    if !report.annotations.is_empty() {
+        html.push_str("<div class=\"section\">\n");
+        html.push_str("<h3>Annotation History</h3>\n");
+        for annotation in &report.annotations {
+            html.push_str(&format!(
+                "<h4>{} {} <code>{}</code></h4>\n",
+                escape_html(annotation.subject_type.as_str()),
+                escape_html(&annotation.subject_id),
+                escape_html(&annotation.annotation_id)
+            ));
+            html.push_str("<table>\n");
+            html.push_str("<tr><th>Revision</th><th>Author</th><th>Written</th><th>Edit Reason</th><th>Note</th></tr>\n");
+            for revision in annotation.revisions.iter().rev() {
+                html.push_str(&format!(
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                    revision.revision,
+                    escape_html(&revision.author),
+                    revision.edited_at.to_rfc3339(),
+                    escape_html(revision.edit_reason.as_deref().unwrap_or("")),
+                    escape_html(&revision.body)
+                ));
+            }
+            html.push_str("</table>\n");
+        }
+        html.push_str("</div>\n");
+    }
     
     // Footer
     html.push_str("<div class=\"footer\">\n");
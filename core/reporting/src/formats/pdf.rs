@@ -80,6 +80,15 @@ pub fn export_pdf(
         Mm(y_position),
         &font_regular,
     );
+    y_position -= line_height;
+    
+    current_layer.use_text(
+        &format!("Redaction Profile: {}", report.metadata.redaction_profile.as_str()),
+        10.0,
+        Mm(margin),
+        Mm(y_position),
+        &font_regular,
+    );
     y_position -= line_height * 2.0;
     
     // Description
@@ -99,6 +108,14 @@ pub fn export_pdf(
         &font_regular,
     );
     y_position -= line_height;
+    current_layer.use_text(
+        &format!("Data Residency: {}", report.summary.residency.label()),
+        10.0,
+        Mm(margin),
+        Mm(y_position),
+        &font_regular,
+    );
+    y_position -= line_height;
     
     if let Some(start) = report.summary.time_range_start {
         current_layer.use_text(
@@ -148,6 +165,42 @@ pub fn export_pdf(
     
     y_position -= line_height;
     
+    // Annotations on their own page(s): one line per revision, current text first
+    if !report.annotations.is_empty() {
+        let (page, layer) = doc.add_page(Mm(210.0), Mm(297.0), "Layer 1");
+        let mut annotation_layer = doc.get_page(page).get_layer(layer);
+        let mut y = 280.0;
+        annotation_layer.use_text("Analyst Annotations:", 12.0, Mm(margin), Mm(y), &font);
+        y -= line_height;
+        for annotation in &report.annotations {
+            let mut lines = vec![(
+                format!("{} {} ({})", annotation.subject_type.as_str(), annotation.subject_id, annotation.annotation_id),
+                &font,
+            )];
+            for revision in annotation.revisions.iter().rev() {
+                lines.push((
+                    format!(
+                        "  rev {} by {} at {}: {}",
+                        revision.revision,
+                        revision.author,
+                        revision.edited_at.to_rfc3339(),
+                        pdf_line(&revision.body)
+                    ),
+                    &font_regular,
+                ));
+            }
+            for (text, line_font) in lines {
+                if y < 30.0 {
+                    let (page, layer) = doc.add_page(Mm(210.0), Mm(297.0), "Layer 1");
+                    annotation_layer = doc.get_page(page).get_layer(layer);
+                    y = 280.0;
+                }
+                annotation_layer.use_text(&text, 9.0, Mm(margin), Mm(y), line_font);
+                y -= line_height;
+            }
+        }
+    }
+    
     // Footer on last page
     let last_layer = doc.get_page(page1).get_layer(layer1);
     last_layer.use_text(
@@ -172,3 +225,13 @@ pub fn export_pdf(
     Ok(())
 }
 
+/// Single PDF text line: newlines folded, long notes truncated (the JSON/HTML/CSV exports carry the full text).
+fn pdf_line(text: &str) -> String {
+    const MAX_CHARS: usize = 90;
+    let folded: String = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if folded.chars().count() <= MAX_CHARS {
+        folded
+    } else {
+        format!("{}...", folded.chars().take(MAX_CHARS).collect::<String>())
+    }
+}
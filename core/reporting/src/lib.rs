@@ -6,12 +6,20 @@
 #[cfg(feature = "future-reporting")]
 mod collector;
 #[cfg(feature = "future-reporting")]
+pub mod annotations;
+#[cfg(feature = "future-reporting")]
 mod evidence_store;
 #[cfg(feature = "future-reporting")]
+mod blob_store;
+#[cfg(feature = "future-reporting")]
+pub mod worm;
+#[cfg(feature = "future-reporting")]
 mod hasher;
 #[cfg(feature = "future-reporting")]
 mod timeline;
 #[cfg(feature = "future-reporting")]
+mod redaction;
+#[cfg(feature = "future-reporting")]
 mod report_builder;
 #[cfg(feature = "future-reporting")]
 mod exporter;
@@ -28,23 +36,49 @@ mod deception_report;
 mod intel_report;
 #[cfg(feature = "future-reporting")]
 mod forensic_report;
+#[cfg(feature = "future-reporting")]
+pub mod scheduler;
+#[cfg(feature = "future-reporting")]
+pub mod distribution;
+#[cfg(feature = "future-reporting")]
+pub mod download_token;
+#[cfg(feature = "future-reporting")]
+pub mod download_server;
+#[cfg(feature = "future-reporting")]
+pub mod notification_templates;
+#[cfg(feature = "future-reporting")]
+pub mod pcap_import;
+#[cfg(feature = "future-reporting")]
+pub mod memory_import;
+#[cfg(feature = "future-reporting")]
+pub mod sandbox_import;
+#[cfg(feature = "future-reporting")]
+pub mod agent_log_import;
 
 // Public API exports - gated behind features
 #[cfg(feature = "future-reporting")]
+pub use annotations::Annotations;
+#[cfg(feature = "future-reporting")]
 pub use collector::EvidenceCollector;
 #[cfg(feature = "future-reporting")]
-pub use evidence_store::EvidenceStore;
+pub use evidence_store::{EvidenceStore, EvidenceStoreOptions};
+#[cfg(feature = "future-reporting")]
+pub use blob_store::StorageStats;
 #[cfg(feature = "future-reporting")]
 pub use hasher::EvidenceHasher;
 #[cfg(feature = "future-reporting")]
 pub use timeline::ForensicTimeline;
 #[cfg(feature = "future-reporting")]
-pub use report_builder::ReportBuilder;
+pub use redaction::{RedactionProfile, Redactor, SensitiveField, Treatment};
+#[cfg(feature = "future-reporting")]
+pub use report_builder::{ReportBuilder, ResidencyClassification, RESIDENCY_METADATA_KEY};
 #[cfg(feature = "future-reporting")]
 pub use exporter::ReportExporter;
 #[cfg(feature = "future-reporting")]
 pub use verifier::EvidenceVerifier;
 #[cfg(feature = "future-retention")]
-pub use retention::RetentionManager;
+pub use retention::{LegalHolds, PurgeEvent, RetentionManager, RetentionPolicy};
+#[cfg(feature = "future-reporting")]
+pub use scheduler::{CronSchedule, GeneratedReport, ReportKind, ReportScheduler, SchedulerConfig};
 pub use errors::ReportingError;
 
@@ -9,12 +9,20 @@ use tracing::{info, error};
 #[cfg(feature = "future-reporting")]
 mod collector;
 #[cfg(feature = "future-reporting")]
+mod annotations;
+#[cfg(feature = "future-reporting")]
 mod evidence_store;
 #[cfg(feature = "future-reporting")]
+mod blob_store;
+#[cfg(feature = "future-reporting")]
+mod worm;
+#[cfg(feature = "future-reporting")]
 mod hasher;
 #[cfg(feature = "future-reporting")]
 mod timeline;
 #[cfg(feature = "future-reporting")]
+mod redaction;
+#[cfg(feature = "future-reporting")]
 mod report_builder;
 #[cfg(feature = "future-reporting")]
 mod exporter;
@@ -25,6 +33,24 @@ mod retention;
 mod errors;
 #[cfg(feature = "future-reporting")]
 mod formats;
+#[cfg(feature = "future-reporting")]
+mod scheduler;
+#[cfg(feature = "future-reporting")]
+mod distribution;
+#[cfg(feature = "future-reporting")]
+mod download_token;
+#[cfg(feature = "future-reporting")]
+mod download_server;
+#[cfg(feature = "future-reporting")]
+mod notification_templates;
+#[cfg(feature = "future-reporting")]
+mod pcap_import;
+#[cfg(feature = "future-reporting")]
+mod memory_import;
+#[cfg(feature = "future-reporting")]
+mod sandbox_import;
+#[cfg(feature = "future-reporting")]
+mod agent_log_import;
 
 use errors::ReportingError;
 
@@ -59,6 +85,173 @@ enum Commands {
         /// Dry run (don't actually delete)
         #[arg(long)]
         dry_run: bool,
+        /// Active legal holds exported from GET /admin/legal-holds (held bundles are never purged)
+        #[arg(long)]
+        legal_holds: Option<PathBuf>,
+    },
+    /// Report evidence payload deduplication and compression savings
+    #[cfg(feature = "future-reporting")]
+    Stats {
+        /// Evidence store path
+        store_path: PathBuf,
+    },
+    /// Release a sealed bundle from WORM protection (requires CAP_LINUX_IMMUTABLE; audited)
+    #[cfg(feature = "future-reporting")]
+    WormRelease {
+        /// Evidence store path
+        store_path: PathBuf,
+        /// Bundle to release
+        bundle_id: String,
+        /// Hash of the sealed bundle (confirms which evidence is released)
+        #[arg(long)]
+        bundle_hash: String,
+        /// Operator performing the release
+        #[arg(long)]
+        actor: String,
+        /// Second person approving the release
+        #[arg(long)]
+        approver: String,
+        /// Reason (legal hold lifted, court order reference, ...)
+        #[arg(long)]
+        reason: String,
+        /// Signed, hash-chained audit log receiving the release records
+        #[arg(long)]
+        audit_log: PathBuf,
+        /// Audit signing key (32 raw bytes; generated on first use). Ignored when RANSOMEYE_AUDIT_PKCS11_* selects an HSM key
+        #[arg(long)]
+        audit_key: PathBuf,
+    },
+    /// Run scheduled report generation and distribution
+    #[cfg(feature = "future-reporting")]
+    Schedule {
+        /// Scheduler configuration (JSON)
+        config: PathBuf,
+        /// Evidence store path
+        store_path: PathBuf,
+        /// Evidence signing key (PKCS#8)
+        #[arg(long)]
+        signing_key: Option<PathBuf>,
+        /// Generate and distribute this report now, then exit
+        #[arg(long)]
+        run_now: Option<String>,
+        /// Seconds between schedule checks
+        #[arg(long, default_value_t = 30)]
+        tick_secs: u64,
+    },
+    /// Serve report artifacts to holders of a download token (audited as chain of custody)
+    #[cfg(feature = "future-reporting")]
+    ServeDownloads {
+        /// Scheduler configuration (JSON) with a downloads section
+        config: PathBuf,
+        /// Listen address (loopback behind the TLS proxy named by downloads.base_url)
+        #[arg(long, default_value = "127.0.0.1:8470")]
+        listen: std::net::SocketAddr,
+        /// Signed, hash-chained audit log receiving the download records
+        #[arg(long)]
+        audit_log: PathBuf,
+        /// Audit signing key (32 raw bytes; generated on first use). Ignored when RANSOMEYE_AUDIT_PKCS11_* selects an HSM key
+        #[arg(long)]
+        audit_key: PathBuf,
+    },
+    /// Issue a download link for one artifact of a generated report
+    #[cfg(feature = "future-reporting")]
+    DownloadLink {
+        /// Scheduler configuration (JSON) with a downloads section
+        config: PathBuf,
+        /// Schedule name
+        schedule: String,
+        /// Report ID
+        report_id: String,
+        /// Artifact file name
+        file_name: String,
+        /// Link lifetime (default: downloads.ttl_secs)
+        #[arg(long)]
+        ttl_secs: Option<u64>,
+        /// Downloads allowed (default: downloads.max_uses)
+        #[arg(long)]
+        max_uses: Option<u32>,
+    },
+    /// Seal packet captures spooled by ingest into the evidence store (non-zero exit if any is rejected)
+    #[cfg(feature = "future-reporting")]
+    ImportCaptures {
+        /// Ingest pcap spool (RANSOMEYE_INGEST_PCAP_SPOOL_DIR)
+        spool: PathBuf,
+        /// Evidence store path
+        store_path: PathBuf,
+        /// Evidence signing key (PKCS#8)
+        #[arg(long)]
+        signing_key: Option<PathBuf>,
+    },
+    /// Write the pcap of an imported capture, verified against its sealed SHA-256
+    #[cfg(feature = "future-reporting")]
+    ExportCapture {
+        /// Evidence store path
+        store_path: PathBuf,
+        /// capture_id of the capture
+        capture_id: String,
+        /// Output file
+        out: PathBuf,
+    },
+    /// Seal memory images spooled by ingest into the evidence store (non-zero exit if any is rejected)
+    #[cfg(feature = "future-reporting")]
+    ImportMemory {
+        /// Ingest memory spool (RANSOMEYE_INGEST_MEMORY_SPOOL_DIR)
+        spool: PathBuf,
+        /// Evidence store path
+        store_path: PathBuf,
+        /// Evidence signing key (PKCS#8)
+        #[arg(long)]
+        signing_key: Option<PathBuf>,
+    },
+    /// Write the memory image of an imported acquisition, every chunk and the image verified
+    #[cfg(feature = "future-reporting")]
+    ExportMemory {
+        /// Evidence store path
+        store_path: PathBuf,
+        /// acquisition_id of the acquisition
+        acquisition_id: String,
+        /// Output file
+        out: PathBuf,
+    },
+    /// Seal sandbox reports spooled by ingest into the evidence store (non-zero exit if any is rejected)
+    #[cfg(feature = "future-reporting")]
+    ImportSandboxReports {
+        /// Ingest sandbox spool (RANSOMEYE_INGEST_SANDBOX_SPOOL_DIR)
+        spool: PathBuf,
+        /// Evidence store path
+        store_path: PathBuf,
+        /// Evidence signing key (PKCS#8)
+        #[arg(long)]
+        signing_key: Option<PathBuf>,
+    },
+    /// Write an imported sandbox report, verified against its sealed SHA-256
+    #[cfg(feature = "future-reporting")]
+    ExportSandboxReport {
+        /// Evidence store path
+        store_path: PathBuf,
+        /// submission_id of the sandbox submission
+        submission_id: String,
+        /// Output file
+        out: PathBuf,
+    },
+    /// Seal agent log segments spooled by ingest into the evidence store (non-zero exit if any is rejected)
+    #[cfg(feature = "future-reporting")]
+    ImportAgentLogs {
+        /// Ingest agent log spool (RANSOMEYE_INGEST_AGENT_LOG_SPOOL_DIR)
+        spool: PathBuf,
+        /// Evidence store path
+        store_path: PathBuf,
+        /// Evidence signing key (PKCS#8)
+        #[arg(long)]
+        signing_key: Option<PathBuf>,
+    },
+    /// Re-verify the sealed log history of an agent (non-zero exit on a fork, bad seal or key change)
+    #[cfg(feature = "future-reporting")]
+    VerifyAgentLogs {
+        /// Evidence store path
+        store_path: PathBuf,
+        /// agent_id of the agent
+        agent_id: String,
     },
 }
 
@@ -78,11 +271,271 @@ fn main() -> Result<(), ReportingError> {
             // Implementation would go here
             println!("Export complete");
         }
-        Commands::Retention { store_path, dry_run } => {
+        #[cfg(feature = "future-retention")]
+        Commands::Retention { store_path, dry_run, legal_holds } => {
+            info!("Enforcing retention policy on {:?} (dry_run: {})", store_path, dry_run);
+            let holds = match &legal_holds {
+                Some(path) => retention::LegalHolds::load(path)?,
+                None => retention::LegalHolds::new(),
+            };
+            let store = evidence_store::EvidenceStore::open(&store_path, None, evidence_store::EvidenceStoreOptions::from_env()?)?;
+            let manager = retention::RetentionManager::new(
+                store,
+                retention::RetentionPolicy::default(),
+                store_path.join("retention").join("purge_ledger.jsonl"),
+            )?
+            .with_legal_holds(holds);
+            let purged = manager.enforce_retention(dry_run)?;
+            println!("Retention enforcement complete: {} bundle(s) purged", purged.len());
+        }
+        #[cfg(not(feature = "future-retention"))]
+        Commands::Retention { store_path, dry_run, .. } => {
             info!("Enforcing retention policy on {:?} (dry_run: {})", store_path, dry_run);
             // Implementation would go here
             println!("Retention enforcement complete");
         }
+        #[cfg(feature = "future-reporting")]
+        Commands::Stats { store_path } => {
+            let store = evidence_store::EvidenceStore::new(&store_path, None)?;
+            let stats = store.storage_stats();
+            println!("Unique payloads:        {}", stats.unique_blobs);
+            println!("Payload references:     {}", stats.references);
+            println!("Logical bytes:          {}", stats.logical_bytes);
+            println!("Stored bytes:           {}", stats.stored_bytes);
+            println!("Saved by deduplication: {}", stats.dedup_saved_bytes());
+            println!("Saved by compression:   {}", stats.compression_saved_bytes());
+            println!("Total savings:          {:.1}%", stats.savings_ratio() * 100.0);
+            if stats.orphaned_blobs > 0 {
+                println!("Orphaned payloads:      {} ({} bytes)", stats.orphaned_blobs, stats.orphaned_bytes);
+            }
+        }
+        #[cfg(feature = "future-reporting")]
+        Commands::WormRelease { store_path, bundle_id, bundle_hash, actor, approver, reason, audit_log, audit_key } => {
+            let store = evidence_store::EvidenceStore::open(&store_path, None, evidence_store::EvidenceStoreOptions::from_env()?)?;
+            let signer = audit::AuditSigner::from_env_or_key_file(&audit_key)
+                .map_err(|e| ReportingError::WormViolation(format!("Failed to load audit key: {}", e)))?;
+            let mut audit = audit::AuditLogger::new(&audit_log, signer)
+                .map_err(|e| ReportingError::WormViolation(format!("Failed to open audit log: {}", e)))?;
+            let request = worm::ReleaseRequest { bundle_id, bundle_hash, actor, approver, reason };
+            let record = store.release_worm(&request, &mut audit)?;
+            println!("Bundle {} released (audit record {})", request.bundle_id, record.audit_record_id);
+        }
+        #[cfg(feature = "future-reporting")]
+        Commands::Schedule { config, store_path, signing_key, run_now, tick_secs } => {
+            let config = scheduler::SchedulerConfig::load(&config)?;
+            let policy_version = std::env::var("RANSOMEYE_POLICY_VERSION").unwrap_or_else(|_| "unknown".to_string());
+            let build_hash = std::env::var("RANSOMEYE_BUILD_HASH").unwrap_or_else(|_| "unknown".to_string());
+            let builder = report_builder::ReportBuilder::new(env!("CARGO_PKG_VERSION"), &policy_version, &build_hash, None);
+            let mut scheduler = scheduler::ReportScheduler::new(config, &store_path, signing_key.as_deref(), builder)?;
+            match run_now {
+                Some(name) => {
+                    let run = scheduler.run_now(&name, chrono::Utc::now())?;
+                    for artifact in &run.report.artifacts {
+                        println!("{}  {}", artifact.sha256, run.report.directory.join(&artifact.file_name).display());
+                    }
+                    for receipt in run.deliveries.iter().filter(|r| !r.delivered) {
+                        error!("Delivery to {} failed: {}", receipt.target, receipt.error.as_deref().unwrap_or("unknown error"));
+                    }
+                }
+                None => scheduler.run_forever(std::time::Duration::from_secs(tick_secs.max(1))),
+            }
+        }
+        #[cfg(feature = "future-reporting")]
+        Commands::ServeDownloads { config, listen, audit_log, audit_key } => {
+            let config = scheduler::SchedulerConfig::load(&config)?;
+            let downloads = config.downloads.as_ref().ok_or_else(|| {
+                ReportingError::InvalidConfiguration("scheduler configuration has no downloads section".to_string())
+            })?;
+            let signer = download_token::DownloadSigner::from_env(&downloads.secret_env)?;
+            let signer_key = audit::AuditSigner::from_env_or_key_file(&audit_key)
+                .map_err(|e| ReportingError::InvalidConfiguration(format!("Failed to load audit key: {}", e)))?;
+            let audit = audit::AuditLogger::new(&audit_log, signer_key)
+                .map_err(|e| ReportingError::InvalidConfiguration(format!("Failed to open audit log: {}", e)))?;
+            let service = download_token::DownloadService::new(signer, &config.output_dir, audit)?;
+            tokio::runtime::Runtime::new()?.block_on(download_server::serve(listen, service))?;
+        }
+        #[cfg(feature = "future-reporting")]
+        Commands::DownloadLink { config, schedule, report_id, file_name, ttl_secs, max_uses } => {
+            let config = scheduler::SchedulerConfig::load(&config)?;
+            let mut downloads = config.downloads.clone().ok_or_else(|| {
+                ReportingError::InvalidConfiguration("scheduler configuration has no downloads section".to_string())
+            })?;
+            downloads.ttl_secs = ttl_secs.unwrap_or(downloads.ttl_secs);
+            downloads.max_uses = max_uses.unwrap_or(downloads.max_uses);
+            downloads.validate()?;
+            let path = download_token::artifact_path(&config.output_dir, &schedule, &report_id, &file_name)?;
+            let data = std::fs::read(&path)?;
+            let artifact = scheduler::ReportArtifact {
+                file_name,
+                sha256: hasher::EvidenceHasher::new().hash_bytes(&data),
+                bytes: data.len() as u64,
+            };
+            let signer = download_token::DownloadSigner::from_env(&downloads.secret_env)?;
+            let link = signer.link(&downloads, &schedule, &report_id, &artifact, chrono::Utc::now())?;
+            println!("{}", link.url);
+            println!("expires {} | {} download(s) | sha256 {}", link.expires_at.to_rfc3339(), link.max_uses, artifact.sha256);
+        }
+        #[cfg(feature = "future-reporting")]
+        Commands::ImportCaptures { spool, store_path, signing_key } => {
+            let store = evidence_store::EvidenceStore::open(
+                &store_path,
+                signing_key.as_deref(),
+                evidence_store::EvidenceStoreOptions::from_env()?,
+            )?;
+            let policy_version = std::env::var("RANSOMEYE_POLICY_VERSION").unwrap_or_else(|_| "unknown".to_string());
+            let collector = collector::EvidenceCollector::new(env!("CARGO_PKG_VERSION"), &policy_version);
+            let outcome =
+                pcap_import::import_captures(&store, &collector, env!("CARGO_PKG_VERSION"), &policy_version, &spool)?;
+            for capture in &outcome.imported {
+                println!("{}  capture {} -> bundle {}", capture.pcap_sha256, capture.capture_id, capture.bundle_id);
+            }
+            for capture in &outcome.rejected {
+                error!("Capture {} rejected: {}", capture.manifest, capture.reason);
+            }
+            if !outcome.rejected.is_empty() {
+                return Err(ReportingError::VerificationFailed(format!(
+                    "{} spooled capture(s) rejected (moved to {})",
+                    outcome.rejected.len(),
+                    spool.join(pcap_import::REJECTED_DIR).display()
+                )));
+            }
+        }
+        #[cfg(feature = "future-reporting")]
+        Commands::ExportCapture { store_path, capture_id, out } => {
+            let store = evidence_store::EvidenceStore::open(&store_path, None, evidence_store::EvidenceStoreOptions::from_env()?)?;
+            let evidence = pcap_import::find_capture(&store, &capture_id)?;
+            let pcap = pcap_import::read_pcap(&evidence)?;
+            std::fs::write(&out, &pcap)?;
+            println!("{}  {}", hasher::EvidenceHasher::new().hash_bytes(&pcap), out.display());
+        }
+        #[cfg(feature = "future-reporting")]
+        Commands::ImportMemory { spool, store_path, signing_key } => {
+            let store = evidence_store::EvidenceStore::open(
+                &store_path,
+                signing_key.as_deref(),
+                evidence_store::EvidenceStoreOptions::from_env()?,
+            )?;
+            let policy_version = std::env::var("RANSOMEYE_POLICY_VERSION").unwrap_or_else(|_| "unknown".to_string());
+            let collector = collector::EvidenceCollector::new(env!("CARGO_PKG_VERSION"), &policy_version);
+            let outcome =
+                memory_import::import_images(&store, &collector, env!("CARGO_PKG_VERSION"), &policy_version, &spool)?;
+            for image in &outcome.imported {
+                println!("{}  memory {} ({} bytes) -> bundle {}", image.image_sha256, image.acquisition_id, image.image_bytes, image.bundle_id);
+            }
+            for image in &outcome.rejected {
+                error!("Memory image {} rejected: {}", image.manifest, image.reason);
+            }
+            if !outcome.rejected.is_empty() {
+                return Err(ReportingError::VerificationFailed(format!(
+                    "{} spooled memory image(s) rejected (moved to {})",
+                    outcome.rejected.len(),
+                    spool.join(memory_import::REJECTED_DIR).display()
+                )));
+            }
+        }
+        #[cfg(feature = "future-reporting")]
+        Commands::ExportMemory { store_path, acquisition_id, out } => {
+            let store = evidence_store::EvidenceStore::open(&store_path, None, evidence_store::EvidenceStoreOptions::from_env()?)?;
+            let evidence = memory_import::find_image(&store, &acquisition_id)?;
+            let bytes = memory_import::export_image(&store, &evidence, &out)?;
+            println!("{}  {} ({} bytes)", evidence.data["image_sha256"].as_str().unwrap_or_default(), out.display(), bytes);
+        }
+        #[cfg(feature = "future-reporting")]
+        Commands::ImportSandboxReports { spool, store_path, signing_key } => {
+            let store = evidence_store::EvidenceStore::open(
+                &store_path,
+                signing_key.as_deref(),
+                evidence_store::EvidenceStoreOptions::from_env()?,
+            )?;
+            let policy_version = std::env::var("RANSOMEYE_POLICY_VERSION").unwrap_or_else(|_| "unknown".to_string());
+            let collector = collector::EvidenceCollector::new(env!("CARGO_PKG_VERSION"), &policy_version);
+            let outcome =
+                sandbox_import::import_reports(&store, &collector, env!("CARGO_PKG_VERSION"), &policy_version, &spool)?;
+            for report in &outcome.imported {
+                println!("{}  sandbox {} ({}) -> bundle {}", report.report_sha256, report.submission_id, report.verdict, report.bundle_id);
+            }
+            for report in &outcome.rejected {
+                error!("Sandbox report {} rejected: {}", report.manifest, report.reason);
+            }
+            if !outcome.rejected.is_empty() {
+                return Err(ReportingError::VerificationFailed(format!(
+                    "{} spooled sandbox report(s) rejected (moved to {})",
+                    outcome.rejected.len(),
+                    spool.join(sandbox_import::REJECTED_DIR).display()
+                )));
+            }
+        }
+        #[cfg(feature = "future-reporting")]
+        Commands::ExportSandboxReport { store_path, submission_id, out } => {
+            let store = evidence_store::EvidenceStore::open(&store_path, None, evidence_store::EvidenceStoreOptions::from_env()?)?;
+            let evidence = sandbox_import::find_report(&store, &submission_id)?;
+            let report = sandbox_import::read_report(&evidence)?;
+            std::fs::write(&out, &report)?;
+            println!("{}  {}", hasher::EvidenceHasher::new().hash_bytes(&report), out.display());
+        }
+        #[cfg(feature = "future-reporting")]
+        Commands::ImportAgentLogs { spool, store_path, signing_key } => {
+            let store = evidence_store::EvidenceStore::open(
+                &store_path,
+                signing_key.as_deref(),
+                evidence_store::EvidenceStoreOptions::from_env()?,
+            )?;
+            let policy_version = std::env::var("RANSOMEYE_POLICY_VERSION").unwrap_or_else(|_| "unknown".to_string());
+            let collector = collector::EvidenceCollector::new(env!("CARGO_PKG_VERSION"), &policy_version);
+            let outcome =
+                agent_log_import::import_segments(&store, &collector, env!("CARGO_PKG_VERSION"), &policy_version, &spool)?;
+            for segment in &outcome.imported {
+                println!(
+                    "{}  agent {} chain {} segment {} -> bundle {}",
+                    segment.segment_id, segment.agent_id, segment.chain_id, segment.segment_index, segment.bundle_id
+                );
+            }
+            for segment in &outcome.rejected {
+                error!("Log segment {} rejected: {}", segment.entry, segment.reason);
+            }
+            if !outcome.rejected.is_empty() {
+                return Err(ReportingError::VerificationFailed(format!(
+                    "{} spooled log segment(s) rejected (moved to {})",
+                    outcome.rejected.len(),
+                    spool.join(agent_log_import::REJECTED_DIR).display()
+                )));
+            }
+        }
+        #[cfg(feature = "future-reporting")]
+        Commands::VerifyAgentLogs { store_path, agent_id } => {
+            let store = evidence_store::EvidenceStore::open(&store_path, None, evidence_store::EvidenceStoreOptions::from_env()?)?;
+            let reports = agent_log_import::verify_agent_logs(&store, &agent_id)?;
+            let mut broken = 0;
+            for report in &reports {
+                println!(
+                    "chain {} (boot {}): {} segment(s), {} line(s), last index {}, {} dropped by agent, {}",
+                    report.chain_id,
+                    report.boot_id,
+                    report.segments,
+                    report.lines,
+                    report.last_index,
+                    report.dropped_by_agent,
+                    if report.is_intact() { "INTACT" } else { "BROKEN" }
+                );
+                for (from, to) in &report.gaps {
+                    println!("  gap: segments {}..={} missing", from, to);
+                }
+                for problem in &report.problems {
+                    error!("Chain {}: {}", report.chain_id, problem);
+                }
+                if !report.is_intact() {
+                    broken += 1;
+                }
+            }
+            if broken > 0 {
+                return Err(ReportingError::VerificationFailed(format!(
+                    "{} of {} log chain(s) of agent {} do not verify",
+                    broken,
+                    reports.len(),
+                    agent_id
+                )));
+            }
+        }
     }
     
     Ok(())
@@ -0,0 +1,324 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_reporting/src/memory_import.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Memory image import - seals agent memory images spooled by ingest into the evidence store as verified chunks with their chain of custody, and exports them back with every chunk and the image hash verified
+
+#![cfg(feature = "future-reporting")]
+
+/*
+ * Memory Image Import
+ *
+ * Ingest spools each memory image a Linux agent streamed as <acquisition_id>/<index>.chunk with
+ * a manifest <acquisition_id>.json (see core/ingest/src/memory_acquisition.rs). `import-memory`
+ * seals every spooled image into its own evidence bundle:
+ *
+ *   source / source_type   linux_agent / memory_image
+ *   timestamp              claimed_at (start of the acquisition)
+ *   data                   the manifest: scope, pid, tool, image SHA-256, the SHA-256 of every
+ *                          chunk and the chain of custody, with a final "sealed" entry
+ *   chunks                 the image chunks as raw blobs, in order (never inline: an image can
+ *                          be far larger than a bundle is allowed to be in memory)
+ *   metadata               acquisition_id, agent_id, scope, incident_id
+ *
+ * Every chunk is checked against its SHA-256 and the whole image against the manifest before
+ * anything is stored; an image that does not verify is moved to <spool>/rejected/ and nothing of
+ * it is sealed. Imported images are removed from the spool once their bundle is sealed.
+ */
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::collector::{CollectedEvidence, EvidenceCollector};
+use crate::errors::ReportingError;
+use crate::evidence_store::EvidenceStore;
+use crate::hasher::EvidenceHasher;
+
+/// Evidence source of imported images
+pub const MEMORY_SOURCE: &str = "linux_agent";
+/// Evidence source_type of imported images
+pub const MEMORY_SOURCE_TYPE: &str = "memory_image";
+/// Spool subdirectory for images that failed verification
+pub const REJECTED_DIR: &str = "rejected";
+
+/// One chunk of the image as recorded by ingest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkEntry {
+    pub index: u32,
+    pub sha256: String,
+    pub bytes: u64,
+}
+
+/// One step in the chain of custody.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustodyEntry {
+    pub at: DateTime<Utc>,
+    pub action: String,
+    pub actor: String,
+    pub detail: String,
+}
+
+/// Manifest written by ingest next to a spooled image.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryManifest {
+    pub acquisition_id: Uuid,
+    pub agent_id: Uuid,
+    pub agent_component_id: String,
+    pub incident_id: Option<Uuid>,
+    /// process or full
+    pub scope: String,
+    pub pid: Option<i32>,
+    pub reason: String,
+    pub requested_by: String,
+    pub approved_by: String,
+    pub requested_at: DateTime<Utc>,
+    pub claimed_at: DateTime<Utc>,
+    pub completed_at: DateTime<Utc>,
+    pub tool: String,
+    pub tool_version: Option<String>,
+    pub image_sha256: String,
+    pub image_bytes: u64,
+    pub chunk_dir: String,
+    pub chunks: Vec<ChunkEntry>,
+    pub custody: Vec<CustodyEntry>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportedImage {
+    pub acquisition_id: Uuid,
+    pub bundle_id: String,
+    pub evidence_id: String,
+    pub image_sha256: String,
+    pub image_bytes: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct RejectedImage {
+    /// Manifest file name (the acquisition id when the manifest could not be read)
+    pub manifest: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ImportOutcome {
+    pub imported: Vec<ImportedImage>,
+    pub rejected: Vec<RejectedImage>,
+}
+
+fn chunk_path(spool_dir: &Path, manifest: &MemoryManifest, chunk: &ChunkEntry) -> PathBuf {
+    spool_dir.join(&manifest.chunk_dir).join(format!("{:08}.chunk", chunk.index))
+}
+
+/// Read one spooled chunk, verified against its manifest entry.
+fn read_spooled_chunk(spool_dir: &Path, manifest: &MemoryManifest, chunk: &ChunkEntry) -> Result<Vec<u8>, String> {
+    let data = fs::read(chunk_path(spool_dir, manifest, chunk))
+        .map_err(|e| format!("chunk {} not readable: {}", chunk.index, e))?;
+    if data.len() as u64 != chunk.bytes {
+        return Err(format!("chunk {} is {} bytes, manifest says {}", chunk.index, data.len(), chunk.bytes));
+    }
+    let sha256 = EvidenceHasher::new().hash_bytes(&data);
+    if !sha256.eq_ignore_ascii_case(&chunk.sha256) {
+        return Err(format!("chunk {} SHA-256 {} does not match manifest {}", chunk.index, sha256, chunk.sha256));
+    }
+    Ok(data)
+}
+
+/// Manifest of one spooled image, with every chunk and the whole image verified against it.
+fn load_image(spool_dir: &Path, manifest_path: &Path) -> Result<MemoryManifest, String> {
+    let manifest: MemoryManifest = serde_json::from_slice(&fs::read(manifest_path).map_err(|e| e.to_string())?)
+        .map_err(|e| format!("unreadable manifest: {}", e))?;
+    if manifest_path.file_stem().and_then(|s| s.to_str()) != Some(manifest.acquisition_id.to_string().as_str()) {
+        return Err(format!("manifest names acquisition {}", manifest.acquisition_id));
+    }
+    // The chunks must sit next to their manifest under its own name
+    if manifest.chunk_dir != manifest.acquisition_id.to_string() {
+        return Err(format!("unexpected chunk directory '{}'", manifest.chunk_dir));
+    }
+    if manifest.chunks.is_empty() {
+        return Err("manifest lists no chunks".to_string());
+    }
+    let mut image = Sha256::new();
+    let mut total = 0u64;
+    for (expected, chunk) in manifest.chunks.iter().enumerate() {
+        if chunk.index as usize != expected {
+            return Err(format!("chunk {} missing from the manifest", expected));
+        }
+        let data = read_spooled_chunk(spool_dir, &manifest, chunk)?;
+        image.update(&data);
+        total += data.len() as u64;
+    }
+    let sha256 = hex::encode(image.finalize());
+    if total != manifest.image_bytes || !sha256.eq_ignore_ascii_case(&manifest.image_sha256) {
+        return Err(format!(
+            "image is {} bytes with SHA-256 {}, manifest says {} bytes with {}",
+            total, sha256, manifest.image_bytes, manifest.image_sha256
+        ));
+    }
+    Ok(manifest)
+}
+
+fn evidence_for(
+    collector: &EvidenceCollector,
+    manifest: &MemoryManifest,
+    chunks: Vec<String>,
+) -> Result<CollectedEvidence, ReportingError> {
+    let mut sealed = manifest.clone();
+    sealed.custody.push(CustodyEntry {
+        at: Utc::now(),
+        action: "sealed".to_string(),
+        actor: "reporting".to_string(),
+        detail: format!("{} chunk(s) sealed in the evidence store", chunks.len()),
+    });
+    let data = serde_json::to_value(&sealed)?;
+    let mut metadata = HashMap::from([
+        ("acquisition_id".to_string(), manifest.acquisition_id.to_string()),
+        ("agent_id".to_string(), manifest.agent_id.to_string()),
+        ("scope".to_string(), manifest.scope.clone()),
+    ]);
+    if let Some(id) = manifest.incident_id {
+        metadata.insert("incident_id".to_string(), id.to_string());
+    }
+    let mut evidence =
+        collector.collect_with_timestamp(MEMORY_SOURCE, MEMORY_SOURCE_TYPE, data, manifest.claimed_at, None, metadata)?;
+    evidence.chunks = chunks;
+    Ok(evidence)
+}
+
+fn reject(spool_dir: &Path, manifest_path: &Path, reason: String) -> Result<RejectedImage, ReportingError> {
+    let rejected_dir = spool_dir.join(REJECTED_DIR);
+    fs::create_dir_all(&rejected_dir)?;
+    let stem = manifest_path.file_stem().and_then(|s| s.to_str()).unwrap_or_default().to_string();
+    for path in [manifest_path.to_path_buf(), spool_dir.join(&stem)] {
+        if let Some(name) = path.file_name() {
+            if path.exists() {
+                fs::rename(&path, rejected_dir.join(name))?;
+            }
+        }
+    }
+    warn!("Rejected spooled memory image {}: {}", stem, reason);
+    Ok(RejectedImage { manifest: stem, reason })
+}
+
+/// Seal every spooled image into the evidence store (one bundle per image).
+/// Store errors abort the import; the image being imported stays in the spool.
+pub fn import_images(
+    store: &EvidenceStore,
+    collector: &EvidenceCollector,
+    engine_version: &str,
+    policy_version: &str,
+    spool_dir: &Path,
+) -> Result<ImportOutcome, ReportingError> {
+    let mut manifests: Vec<PathBuf> = fs::read_dir(spool_dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && p.extension().and_then(|e| e.to_str()) == Some("json"))
+        .collect();
+    manifests.sort();
+
+    let mut outcome = ImportOutcome::default();
+    for manifest_path in manifests {
+        let manifest = match load_image(spool_dir, &manifest_path) {
+            Ok(manifest) => manifest,
+            Err(reason) => {
+                outcome.rejected.push(reject(spool_dir, &manifest_path, reason)?);
+                continue;
+            }
+        };
+        // Verified above; each chunk is re-read and checked again on its way into the store
+        let mut chunks = Vec::with_capacity(manifest.chunks.len());
+        for chunk in &manifest.chunks {
+            let data = read_spooled_chunk(spool_dir, &manifest, chunk).map_err(ReportingError::EvidenceCorrupted)?;
+            chunks.push(store.put_chunk(&data)?);
+        }
+        let evidence = evidence_for(collector, &manifest, chunks)?;
+        let evidence_id = evidence.evidence_id.clone();
+        let bundle_id = store.create_bundle(engine_version, policy_version)?;
+        store.add_evidence(&bundle_id, evidence)?;
+        store.seal_bundle(&bundle_id)?;
+        // Sealed: the spool copy is no longer needed
+        fs::remove_file(&manifest_path)?;
+        fs::remove_dir_all(spool_dir.join(&manifest.chunk_dir))?;
+        info!(
+            "Imported memory image {} | bundle={} | agent={} | scope={} | bytes={} | sha256={}",
+            manifest.acquisition_id, bundle_id, manifest.agent_id, manifest.scope, manifest.image_bytes, manifest.image_sha256
+        );
+        outcome.imported.push(ImportedImage {
+            acquisition_id: manifest.acquisition_id,
+            bundle_id,
+            evidence_id,
+            image_sha256: manifest.image_sha256,
+            image_bytes: manifest.image_bytes,
+        });
+    }
+    Ok(outcome)
+}
+
+/// Write the image of an imported acquisition to `out`, every chunk verified against the
+/// SHA-256 sealed with it and the whole image against the sealed image SHA-256.
+/// Returns the bytes written. Nothing is left at `out` if verification fails.
+pub fn export_image(store: &EvidenceStore, evidence: &CollectedEvidence, out: &Path) -> Result<u64, ReportingError> {
+    if evidence.source_type != MEMORY_SOURCE_TYPE {
+        return Err(ReportingError::MissingEvidence(format!(
+            "Evidence {} is {}, not a memory image",
+            evidence.evidence_id, evidence.source_type
+        )));
+    }
+    let manifest: MemoryManifest = serde_json::from_value(evidence.data.clone())?;
+    let sealed: Vec<&str> = manifest.chunks.iter().map(|c| c.sha256.as_str()).collect();
+    if sealed.len() != evidence.chunks.len()
+        || sealed.iter().zip(&evidence.chunks).any(|(a, b)| !a.eq_ignore_ascii_case(b))
+    {
+        return Err(ReportingError::EvidenceCorrupted(format!(
+            "Memory evidence {}: chunk list does not match its manifest",
+            evidence.evidence_id
+        )));
+    }
+
+    let tmp = out.with_extension("partial");
+    let written = (|| {
+        let mut file = fs::File::create(&tmp)?;
+        let mut image = Sha256::new();
+        let mut total = 0u64;
+        for digest in &evidence.chunks {
+            let data = store.read_chunk(digest)?;
+            image.update(&data);
+            file.write_all(&data)?;
+            total += data.len() as u64;
+        }
+        file.sync_all()?;
+        let actual = hex::encode(image.finalize());
+        let expected = manifest.image_sha256.to_ascii_lowercase();
+        if actual != expected {
+            return Err(ReportingError::HashMismatch { expected, actual });
+        }
+        Ok(total)
+    })();
+    match written {
+        Ok(total) => {
+            fs::rename(&tmp, out)?;
+            Ok(total)
+        }
+        Err(e) => {
+            let _ = fs::remove_file(&tmp);
+            Err(e)
+        }
+    }
+}
+
+/// The sealed evidence of a memory image, by acquisition id.
+pub fn find_image(store: &EvidenceStore, acquisition_id: &str) -> Result<CollectedEvidence, ReportingError> {
+    store
+        .get_all_bundles()
+        .into_iter()
+        .filter(|b| b.is_sealed)
+        .flat_map(|b| b.evidence_items)
+        .find(|e| {
+            e.source_type == MEMORY_SOURCE_TYPE
+                && e.metadata.get("acquisition_id").is_some_and(|id| id.eq_ignore_ascii_case(acquisition_id))
+        })
+        .ok_or_else(|| ReportingError::MissingEvidence(format!("No sealed memory image {}", acquisition_id)))
+}
@@ -0,0 +1,446 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_reporting/src/notification_templates.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Notification templating - renders report and alert email subjects and bodies from Tera templates, with per-tenant and per-locale overrides validated at load time and built-in English fallbacks
+
+#![cfg(feature = "future-reporting")]
+
+/*
+ * Notification Templates
+ *
+ * Report and alert notifications are rendered from Tera templates. The built-in templates
+ * (English) are compiled into the binary. Operators override them per tenant and locale with
+ * files under the configured template directory:
+ *
+ *   <dir>/<scope>/<locale>/<template>.tera
+ *
+ *   scope     a tenant id, or "default" for every tenant
+ *   locale    language tag such as "en", "de" or "pt-br" (matched case-insensitively)
+ *   template  report_subject, report_body, alert_subject or alert_body
+ *
+ * For a tenant and locale the first existing override wins, in this order: tenant/locale,
+ * tenant/language, default/locale, default/language ("pt-br" falls back to "pt"). Without an
+ * override the built-in template is used.
+ *
+ * Every override is compiled and rendered against a sample notification when the templates are
+ * loaded. Syntax errors, unknown template files and variables a notification does not provide
+ * fail the load, and with it scheduler startup. An override that still fails to render a real
+ * notification is logged and the built-in template is used instead: a broken translation never
+ * costs a notification.
+ */
+
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tera::{Context, Tera};
+use tracing::warn;
+
+use crate::download_token::DownloadLink;
+use crate::errors::ReportingError;
+use crate::redaction::RedactionProfile;
+use crate::scheduler::{GeneratedReport, ReportArtifact, ReportKind};
+
+/// Scope directory whose overrides apply to every tenant.
+pub const DEFAULT_SCOPE: &str = "default";
+/// Locale of the built-in templates.
+pub const DEFAULT_LOCALE: &str = "en";
+const TEMPLATE_EXTENSION: &str = "tera";
+
+const REPORT_SUBJECT: &str = "[RansomEye] {{ title }} - {{ window_end_date }}";
+
+const REPORT_BODY: &str = "RansomEye scheduled report: {{ title }}
+Schedule: {{ schedule }}
+Report ID: {{ report_id }}
+Window (UTC): {{ window_start }} to {{ window_end }}
+Evidence items: {{ evidence_items }}
+Redaction profile: {{ redaction_profile }}
+Data residency: {{ residency }}
+Evidence bundle: {{ evidence_bundle_id }} (SHA-256 {{ evidence_bundle_hash }})
+
+Artifact integrity (SHA-256, verify with `sha256sum -c`):
+{% for artifact in artifacts -%}
+{{ artifact.sha256 }}  {{ artifact.file_name }}
+{% endfor -%}
+{% if downloads %}
+Download links (artifacts are not attached):
+{% for link in downloads -%}
+{{ link.file_name }}
+  {{ link.url }} (expires {{ link.expires_at }}, {{ link.max_uses }} download(s))
+{% endfor -%}
+{% endif %}
+© RansomEye.Tech | Support: Gagan@RansomEye.Tech
+";
+
+const ALERT_SUBJECT: &str = "[RansomEye] {{ severity | upper }} alert: {{ title }}{% if host %} on {{ host }}{% endif %}";
+
+const ALERT_BODY: &str = "RansomEye alert: {{ title }}
+Severity: {{ severity }}
+Detected (UTC): {{ detected_at }}
+Alert ID: {{ alert_id }}
+{% if host %}Host: {{ host }}
+{% endif %}{% if agent_id %}Agent: {{ agent_id }}
+{% endif %}{% if incident_id %}Incident: {{ incident_id }}
+{% endif %}{% if mitre_techniques %}MITRE ATT&CK: {{ mitre_techniques | join(sep=\", \") }}
+{% endif %}
+{{ summary }}
+{% if link %}
+Details: {{ link }}
+{% endif %}
+© RansomEye.Tech | Support: Gagan@RansomEye.Tech
+";
+
+fn default_locale() -> String {
+    DEFAULT_LOCALE.to_string()
+}
+
+/// `templates` section of the scheduler configuration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateConfig {
+    /// Override directory (<dir>/<scope>/<locale>/<template>.tera)
+    pub dir: PathBuf,
+    /// Locale of reports that do not set one
+    #[serde(default = "default_locale")]
+    pub default_locale: String,
+}
+
+/// Templates a notification is rendered from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TemplateName {
+    ReportSubject,
+    ReportBody,
+    AlertSubject,
+    AlertBody,
+}
+
+impl TemplateName {
+    pub const ALL: [TemplateName; 4] =
+        [TemplateName::ReportSubject, TemplateName::ReportBody, TemplateName::AlertSubject, TemplateName::AlertBody];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TemplateName::ReportSubject => "report_subject",
+            TemplateName::ReportBody => "report_body",
+            TemplateName::AlertSubject => "alert_subject",
+            TemplateName::AlertBody => "alert_body",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.as_str() == name)
+    }
+
+    fn builtin_source(&self) -> &'static str {
+        match self {
+            TemplateName::ReportSubject => REPORT_SUBJECT,
+            TemplateName::ReportBody => REPORT_BODY,
+            TemplateName::AlertSubject => ALERT_SUBJECT,
+            TemplateName::AlertBody => ALERT_BODY,
+        }
+    }
+
+    /// Context exercising every variable the template may use.
+    fn sample_context(&self) -> Context {
+        match self {
+            TemplateName::ReportSubject | TemplateName::ReportBody => report_context(&sample_report()),
+            TemplateName::AlertSubject | TemplateName::AlertBody => alert_context(&sample_alert()),
+        }
+    }
+}
+
+/// An alert as notifications present it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertNotification {
+    pub alert_id: String,
+    /// "critical", "high", "medium" or "low"
+    pub severity: String,
+    pub title: String,
+    pub summary: String,
+    pub detected_at: DateTime<Utc>,
+    #[serde(default)]
+    pub host: Option<String>,
+    #[serde(default)]
+    pub agent_id: Option<String>,
+    #[serde(default)]
+    pub incident_id: Option<String>,
+    /// MITRE ATT&CK technique ids (T1486, ...)
+    #[serde(default)]
+    pub mitre_techniques: Vec<String>,
+    /// Console link to the alert
+    #[serde(default)]
+    pub link: Option<String>,
+}
+
+/// Template variables of a report notification. Timestamps are RFC 3339 (UTC).
+pub fn report_context(report: &GeneratedReport) -> Context {
+    let mut ctx = Context::new();
+    ctx.insert("title", &report.title);
+    ctx.insert("schedule", &report.schedule);
+    ctx.insert("kind", &report.kind);
+    ctx.insert("report_id", &report.report_id);
+    ctx.insert("generated_at", &report.generated_at.to_rfc3339());
+    ctx.insert("window_start", &report.window_start.to_rfc3339());
+    ctx.insert("window_end", &report.window_end.to_rfc3339());
+    ctx.insert("window_end_date", &report.window_end.format("%Y-%m-%d").to_string());
+    ctx.insert("evidence_items", &report.evidence_items);
+    ctx.insert("redaction_profile", report.redaction_profile.as_str());
+    ctx.insert("residency", &report.residency);
+    ctx.insert("evidence_bundle_id", &report.evidence_bundle_id);
+    ctx.insert("evidence_bundle_hash", &report.evidence_bundle_hash);
+    ctx.insert("artifacts", &report.artifacts);
+    let downloads: Vec<serde_json::Value> = report
+        .downloads
+        .iter()
+        .map(|link| {
+            serde_json::json!({
+                "file_name": link.file_name,
+                "url": link.url,
+                "expires_at": link.expires_at.to_rfc3339(),
+                "max_uses": link.max_uses,
+            })
+        })
+        .collect();
+    ctx.insert("downloads", &downloads);
+    ctx
+}
+
+/// Template variables of an alert notification. Absent optional fields are null.
+pub fn alert_context(alert: &AlertNotification) -> Context {
+    let mut ctx = Context::new();
+    ctx.insert("alert_id", &alert.alert_id);
+    ctx.insert("severity", &alert.severity);
+    ctx.insert("title", &alert.title);
+    ctx.insert("summary", &alert.summary);
+    ctx.insert("detected_at", &alert.detected_at.to_rfc3339());
+    ctx.insert("host", &alert.host);
+    ctx.insert("agent_id", &alert.agent_id);
+    ctx.insert("incident_id", &alert.incident_id);
+    ctx.insert("mitre_techniques", &alert.mitre_techniques);
+    ctx.insert("link", &alert.link);
+    ctx
+}
+
+fn sample_report() -> GeneratedReport {
+    let window_end = Utc.with_ymd_and_hms(2026, 1, 2, 6, 0, 0).unwrap();
+    GeneratedReport {
+        schedule: "daily-exec".to_string(),
+        kind: ReportKind::ExecutiveSummary,
+        title: "Executive Summary".to_string(),
+        report_id: "report-sample".to_string(),
+        generated_at: window_end,
+        window_start: Utc.with_ymd_and_hms(2026, 1, 1, 6, 0, 0).unwrap(),
+        window_end,
+        evidence_items: 1,
+        redaction_profile: RedactionProfile::Executive,
+        residency: "unclassified".to_string(),
+        artifacts: vec![ReportArtifact {
+            file_name: "report-sample_report.pdf".to_string(),
+            sha256: "0".repeat(64),
+            bytes: 1,
+        }],
+        evidence_bundle_id: "bundle-sample".to_string(),
+        evidence_bundle_hash: "0".repeat(64),
+        downloads: vec![DownloadLink {
+            file_name: "report-sample_report.pdf".to_string(),
+            url: "https://reports.example.com/reports/download/sample".to_string(),
+            expires_at: window_end,
+            max_uses: 1,
+        }],
+        directory: PathBuf::new(),
+    }
+}
+
+fn sample_alert() -> AlertNotification {
+    AlertNotification {
+        alert_id: "alert-sample".to_string(),
+        severity: "critical".to_string(),
+        title: "Mass file encryption".to_string(),
+        summary: "Sample alert".to_string(),
+        detected_at: Utc.with_ymd_and_hms(2026, 1, 1, 6, 0, 0).unwrap(),
+        host: Some("host-1".to_string()),
+        agent_id: Some("agent-1".to_string()),
+        incident_id: Some("incident-1".to_string()),
+        mitre_techniques: vec!["T1486".to_string()],
+        link: Some("https://console.example.com/alerts/alert-sample".to_string()),
+    }
+}
+
+/// Tenant ids and locales become directory names.
+fn valid_segment(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Reject a tenant id that cannot name an override directory.
+pub fn validate_tenant(tenant: &str) -> Result<(), ReportingError> {
+    if !valid_segment(tenant) || tenant == DEFAULT_SCOPE {
+        return Err(ReportingError::InvalidConfiguration(format!(
+            "tenant '{}' must be [A-Za-z0-9_-]+ and not '{}'",
+            tenant, DEFAULT_SCOPE
+        )));
+    }
+    Ok(())
+}
+
+/// Reject a locale that cannot name an override directory.
+pub fn validate_locale(locale: &str) -> Result<(), ReportingError> {
+    if !valid_segment(locale) {
+        return Err(ReportingError::InvalidConfiguration(format!("locale '{}' must be [A-Za-z0-9_-]+", locale)));
+    }
+    Ok(())
+}
+
+/// Tera reports the cause of a render failure in the source chain.
+fn describe(err: &tera::Error) -> String {
+    let mut message = err.to_string();
+    let mut source = std::error::Error::source(err);
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
+
+fn override_key(scope: &str, locale: &str, name: TemplateName) -> String {
+    format!("{}/{}/{}", scope, locale, name.as_str())
+}
+
+fn subdirectories(dir: &Path) -> Result<Vec<(String, PathBuf)>, ReportingError> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') {
+            continue;
+        }
+        if !entry.file_type()?.is_dir() {
+            return Err(ReportingError::InvalidConfiguration(format!(
+                "unexpected file {} in template directory (expected <scope>/<locale>/<template>.{})",
+                entry.path().display(),
+                TEMPLATE_EXTENSION
+            )));
+        }
+        entries.push((name, entry.path()));
+    }
+    entries.sort();
+    Ok(entries)
+}
+
+/// Built-in templates plus the operator overrides loaded from the template directory.
+pub struct NotificationTemplates {
+    builtin: Tera,
+    /// Keyed "<scope>/<locale>/<template>", locale lowercased
+    overrides: Tera,
+    default_locale: String,
+}
+
+impl NotificationTemplates {
+    /// Built-in English templates only.
+    pub fn builtin() -> Self {
+        let mut builtin = Tera::default();
+        builtin
+            .add_raw_templates(TemplateName::ALL.iter().map(|t| (t.as_str(), t.builtin_source())))
+            .expect("built-in notification templates compile");
+        Self { builtin, overrides: Tera::default(), default_locale: DEFAULT_LOCALE.to_string() }
+    }
+
+    /// Built-in templates plus every override under `config.dir`. Any override that does not
+    /// compile or does not render the sample notification fails the load.
+    pub fn load(config: &TemplateConfig) -> Result<Self, ReportingError> {
+        validate_locale(&config.default_locale)?;
+        let mut templates = Self::builtin();
+        templates.default_locale = config.default_locale.to_ascii_lowercase();
+        let invalid = |path: &Path, msg: String| ReportingError::InvalidConfiguration(format!("{}: {}", path.display(), msg));
+
+        for (scope, scope_dir) in subdirectories(&config.dir)? {
+            if scope != DEFAULT_SCOPE {
+                validate_tenant(&scope).map_err(|e| invalid(&scope_dir, e.to_string()))?;
+            }
+            for (locale, locale_dir) in subdirectories(&scope_dir)? {
+                validate_locale(&locale).map_err(|e| invalid(&locale_dir, e.to_string()))?;
+                let locale = locale.to_ascii_lowercase();
+                for entry in fs::read_dir(&locale_dir)? {
+                    let path = entry?.path();
+                    let file_name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+                    if file_name.starts_with('.') {
+                        continue;
+                    }
+                    let name = file_name
+                        .strip_suffix(&format!(".{}", TEMPLATE_EXTENSION))
+                        .and_then(TemplateName::parse)
+                        .ok_or_else(|| {
+                            invalid(&path, format!("unknown template (expected one of report_subject, report_body, alert_subject, alert_body with .{})", TEMPLATE_EXTENSION))
+                        })?;
+                    let source = fs::read_to_string(&path)?;
+                    let key = override_key(&scope, &locale, name);
+                    if templates.overrides.get_template_names().any(|n| n == key) {
+                        return Err(invalid(&path, format!("duplicate override {} (locales differ only in case)", key)));
+                    }
+                    templates.overrides.add_raw_template(&key, &source).map_err(|e| invalid(&path, describe(&e)))?;
+                    templates
+                        .overrides
+                        .render(&key, &name.sample_context())
+                        .map_err(|e| invalid(&path, describe(&e)))?;
+                }
+            }
+        }
+        Ok(templates)
+    }
+
+    /// Number of loaded overrides.
+    pub fn override_count(&self) -> usize {
+        self.overrides.get_template_names().count()
+    }
+
+    /// Override keys in lookup order for a tenant and locale (None: default locale).
+    fn candidates(&self, name: TemplateName, tenant: Option<&str>, locale: Option<&str>) -> Vec<String> {
+        let locale = locale.unwrap_or(&self.default_locale).to_ascii_lowercase();
+        let mut locales = vec![locale.clone()];
+        if let Some((language, _)) = locale.split_once(['-', '_']) {
+            locales.push(language.to_string());
+        }
+        let scopes = tenant.into_iter().chain(std::iter::once(DEFAULT_SCOPE));
+        scopes
+            .flat_map(|scope| locales.iter().map(move |l| override_key(scope, l, name)))
+            .collect()
+    }
+
+    /// Key of the template that renders `name` for a tenant and locale: an override key, or
+    /// "builtin/<template>".
+    pub fn resolve(&self, name: TemplateName, tenant: Option<&str>, locale: Option<&str>) -> String {
+        self.candidates(name, tenant, locale)
+            .into_iter()
+            .find(|key| self.overrides.get_template_names().any(|n| n == key))
+            .unwrap_or_else(|| format!("builtin/{}", name.as_str()))
+    }
+
+    /// Render `name` for a tenant and locale, falling back to the built-in template when the
+    /// override fails.
+    pub fn render(&self, name: TemplateName, tenant: Option<&str>, locale: Option<&str>, ctx: &Context) -> String {
+        let key = self.resolve(name, tenant, locale);
+        if !key.starts_with("builtin/") {
+            match self.overrides.render(&key, ctx) {
+                Ok(rendered) => return rendered,
+                Err(e) => warn!("Notification template {} failed, using built-in {}: {}", key, name.as_str(), describe(&e)),
+            }
+        }
+        // Built-in templates only reference variables the context builders always set
+        self.builtin.render(name.as_str(), ctx).expect("built-in notification template renders")
+    }
+
+    /// Subjects are one line; a trailing newline left by an editor is dropped.
+    pub fn report_subject(&self, report: &GeneratedReport, tenant: Option<&str>, locale: Option<&str>) -> String {
+        self.render(TemplateName::ReportSubject, tenant, locale, &report_context(report)).trim_end().to_string()
+    }
+
+    pub fn report_body(&self, report: &GeneratedReport, tenant: Option<&str>, locale: Option<&str>) -> String {
+        self.render(TemplateName::ReportBody, tenant, locale, &report_context(report))
+    }
+
+    pub fn alert_subject(&self, alert: &AlertNotification, tenant: Option<&str>, locale: Option<&str>) -> String {
+        self.render(TemplateName::AlertSubject, tenant, locale, &alert_context(alert)).trim_end().to_string()
+    }
+
+    pub fn alert_body(&self, alert: &AlertNotification, tenant: Option<&str>, locale: Option<&str>) -> String {
+        self.render(TemplateName::AlertBody, tenant, locale, &alert_context(alert))
+    }
+}
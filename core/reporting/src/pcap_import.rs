@@ -0,0 +1,232 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_reporting/src/pcap_import.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Triggered packet capture import - seals pcaps spooled by ingest into the evidence store, linked to their detection and incident, and reads them back with their hash verified
+
+#![cfg(feature = "future-reporting")]
+
+/*
+ * Packet Capture Import
+ *
+ * Ingest spools each pcap uploaded by a DPI probe as <capture_id>.pcap with a manifest
+ * <capture_id>.json (see core/ingest/src/pcap_capture.rs). `import-captures` seals every
+ * spooled capture into its own evidence bundle:
+ *
+ *   source / source_type   dpi_probe / pcap_capture
+ *   timestamp              claimed_at (start of the capture)
+ *   data                   the manifest and the pcap itself (pcap_base64)
+ *   metadata               capture_id, detection_id, incident_id, host_ip
+ *
+ * The detection and incident ids in the metadata link the capture to the incident in reports
+ * and annotations. The pcap is only taken when its size and SHA-256 match the manifest; a
+ * capture that does not is moved to <spool>/rejected/ and nothing of it is sealed. Imported
+ * captures are removed from the spool once their bundle is sealed.
+ */
+
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::collector::{CollectedEvidence, EvidenceCollector};
+use crate::errors::ReportingError;
+use crate::evidence_store::EvidenceStore;
+use crate::hasher::EvidenceHasher;
+
+/// Evidence source of imported captures
+pub const PCAP_SOURCE: &str = "dpi_probe";
+/// Evidence source_type of imported captures
+pub const PCAP_SOURCE_TYPE: &str = "pcap_capture";
+/// Key of the base64 pcap in the evidence data (left out of report evidence details)
+pub const PCAP_DATA_KEY: &str = "pcap_base64";
+/// Spool subdirectory for captures that failed verification
+pub const REJECTED_DIR: &str = "rejected";
+
+/// Manifest written by ingest next to a spooled pcap.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureManifest {
+    pub capture_id: Uuid,
+    pub detection_id: Option<Uuid>,
+    pub incident_id: Option<Uuid>,
+    pub host_ip: String,
+    pub bpf_filter: String,
+    pub probe_agent_id: Uuid,
+    pub probe_component_id: String,
+    pub requested_at: DateTime<Utc>,
+    pub claimed_at: DateTime<Utc>,
+    pub uploaded_at: DateTime<Utc>,
+    pub pcap_file: String,
+    pub pcap_sha256: String,
+    pub pcap_bytes: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportedCapture {
+    pub capture_id: Uuid,
+    pub bundle_id: String,
+    pub evidence_id: String,
+    pub pcap_sha256: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct RejectedCapture {
+    /// Manifest file name (the capture id when the manifest could not be read)
+    pub manifest: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ImportOutcome {
+    pub imported: Vec<ImportedCapture>,
+    pub rejected: Vec<RejectedCapture>,
+}
+
+/// Manifest and pcap of one spooled capture, verified against each other.
+fn load_capture(spool_dir: &Path, manifest_path: &Path) -> Result<(CaptureManifest, Vec<u8>), String> {
+    let manifest: CaptureManifest = serde_json::from_slice(&fs::read(manifest_path).map_err(|e| e.to_string())?)
+        .map_err(|e| format!("unreadable manifest: {}", e))?;
+    if manifest_path.file_stem().and_then(|s| s.to_str()) != Some(manifest.capture_id.to_string().as_str()) {
+        return Err(format!("manifest names capture {}", manifest.capture_id));
+    }
+    // The pcap must sit next to its manifest under its own name
+    if manifest.pcap_file != format!("{}.pcap", manifest.capture_id) {
+        return Err(format!("unexpected pcap file name '{}'", manifest.pcap_file));
+    }
+    let pcap = fs::read(spool_dir.join(&manifest.pcap_file)).map_err(|e| format!("pcap not readable: {}", e))?;
+    if pcap.len() as u64 != manifest.pcap_bytes {
+        return Err(format!("pcap is {} bytes, manifest says {}", pcap.len(), manifest.pcap_bytes));
+    }
+    let sha256 = EvidenceHasher::new().hash_bytes(&pcap);
+    if !sha256.eq_ignore_ascii_case(&manifest.pcap_sha256) {
+        return Err(format!("pcap SHA-256 {} does not match manifest {}", sha256, manifest.pcap_sha256));
+    }
+    Ok((manifest, pcap))
+}
+
+fn evidence_for(
+    collector: &EvidenceCollector,
+    manifest: &CaptureManifest,
+    pcap: &[u8],
+) -> Result<CollectedEvidence, ReportingError> {
+    let mut data = serde_json::to_value(manifest)?;
+    data[PCAP_DATA_KEY] = serde_json::Value::String(general_purpose::STANDARD.encode(pcap));
+    let mut metadata = HashMap::from([
+        ("capture_id".to_string(), manifest.capture_id.to_string()),
+        ("host_ip".to_string(), manifest.host_ip.clone()),
+    ]);
+    if let Some(id) = manifest.detection_id {
+        metadata.insert("detection_id".to_string(), id.to_string());
+    }
+    if let Some(id) = manifest.incident_id {
+        metadata.insert("incident_id".to_string(), id.to_string());
+    }
+    collector.collect_with_timestamp(PCAP_SOURCE, PCAP_SOURCE_TYPE, data, manifest.claimed_at, None, metadata)
+}
+
+fn reject(spool_dir: &Path, manifest_path: &Path, reason: String) -> Result<RejectedCapture, ReportingError> {
+    let rejected_dir = spool_dir.join(REJECTED_DIR);
+    fs::create_dir_all(&rejected_dir)?;
+    let stem = manifest_path.file_stem().and_then(|s| s.to_str()).unwrap_or_default().to_string();
+    for path in [manifest_path.to_path_buf(), spool_dir.join(format!("{}.pcap", stem))] {
+        if let Some(name) = path.file_name() {
+            if path.exists() {
+                fs::rename(&path, rejected_dir.join(name))?;
+            }
+        }
+    }
+    warn!("Rejected spooled capture {}: {}", stem, reason);
+    Ok(RejectedCapture { manifest: stem, reason })
+}
+
+/// Seal every spooled capture into the evidence store (one bundle per capture).
+/// Store errors abort the import; the capture being imported stays in the spool.
+pub fn import_captures(
+    store: &EvidenceStore,
+    collector: &EvidenceCollector,
+    engine_version: &str,
+    policy_version: &str,
+    spool_dir: &Path,
+) -> Result<ImportOutcome, ReportingError> {
+    let mut manifests: Vec<PathBuf> = fs::read_dir(spool_dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && p.extension().and_then(|e| e.to_str()) == Some("json"))
+        .collect();
+    manifests.sort();
+
+    let mut outcome = ImportOutcome::default();
+    for manifest_path in manifests {
+        let (manifest, pcap) = match load_capture(spool_dir, &manifest_path) {
+            Ok(capture) => capture,
+            Err(reason) => {
+                outcome.rejected.push(reject(spool_dir, &manifest_path, reason)?);
+                continue;
+            }
+        };
+        let evidence = match evidence_for(collector, &manifest, &pcap) {
+            Ok(evidence) => evidence,
+            Err(e) => {
+                outcome.rejected.push(reject(spool_dir, &manifest_path, e.to_string())?);
+                continue;
+            }
+        };
+        let evidence_id = evidence.evidence_id.clone();
+        let bundle_id = store.create_bundle(engine_version, policy_version)?;
+        store.add_evidence(&bundle_id, evidence)?;
+        store.seal_bundle(&bundle_id)?;
+        // Sealed: the spool copy is no longer needed
+        fs::remove_file(&manifest_path)?;
+        fs::remove_file(spool_dir.join(&manifest.pcap_file))?;
+        info!(
+            "Imported capture {} | bundle={} | host={} | bytes={} | sha256={}",
+            manifest.capture_id, bundle_id, manifest.host_ip, manifest.pcap_bytes, manifest.pcap_sha256
+        );
+        outcome.imported.push(ImportedCapture {
+            capture_id: manifest.capture_id,
+            bundle_id,
+            evidence_id,
+            pcap_sha256: manifest.pcap_sha256,
+        });
+    }
+    Ok(outcome)
+}
+
+/// The pcap of an imported capture, verified against the SHA-256 sealed with it.
+pub fn read_pcap(evidence: &CollectedEvidence) -> Result<Vec<u8>, ReportingError> {
+    if evidence.source_type != PCAP_SOURCE_TYPE {
+        return Err(ReportingError::MissingEvidence(format!(
+            "Evidence {} is {}, not a packet capture",
+            evidence.evidence_id, evidence.source_type
+        )));
+    }
+    let field = |key: &str| {
+        evidence.data.get(key).and_then(|v| v.as_str()).ok_or_else(|| {
+            ReportingError::EvidenceCorrupted(format!("Capture evidence {} has no {}", evidence.evidence_id, key))
+        })
+    };
+    let pcap = general_purpose::STANDARD
+        .decode(field(PCAP_DATA_KEY)?)
+        .map_err(|e| ReportingError::EvidenceCorrupted(format!("Capture evidence {}: {}", evidence.evidence_id, e)))?;
+    let expected = field("pcap_sha256")?.to_ascii_lowercase();
+    let actual = EvidenceHasher::new().hash_bytes(&pcap);
+    if actual != expected {
+        return Err(ReportingError::HashMismatch { expected, actual });
+    }
+    Ok(pcap)
+}
+
+/// The sealed evidence of a capture, by capture id.
+pub fn find_capture(store: &EvidenceStore, capture_id: &str) -> Result<CollectedEvidence, ReportingError> {
+    store
+        .get_all_bundles()
+        .into_iter()
+        .filter(|b| b.is_sealed)
+        .flat_map(|b| b.evidence_items)
+        .find(|e| {
+            e.source_type == PCAP_SOURCE_TYPE
+                && e.metadata.get("capture_id").is_some_and(|id| id.eq_ignore_ascii_case(capture_id))
+        })
+        .ok_or_else(|| ReportingError::MissingEvidence(format!("No sealed capture {}", capture_id)))
+}
@@ -0,0 +1,227 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_reporting/src/redaction.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Role-scoped redaction profiles (full forensic, SOC, executive) - classify command lines, usernames, IP addresses and file paths in evidence content and include, pseudonymize or redact them for the report's audience
+
+#![cfg(feature = "future-reporting")]
+
+/*
+ * Redaction Profiles
+ *
+ * The report builder applies one profile to everything it copies out of evidence (evidence
+ * details section, timeline descriptions and metadata); sealed evidence itself is never changed.
+ *
+ *                  command lines  usernames     IP addresses  file paths  evidence details
+ *   full_forensic  include        include       include       include     yes
+ *   soc            include        pseudonymize  include       include     yes
+ *   executive      redact         redact        redact        redact      no
+ *
+ * Fields are recognized by JSON key (cmdline, *_command_line, user, *_username, src_ip,
+ * remote_addr, file_path, executable, ...). Free text (and values under other keys) is scanned
+ * for IP addresses and absolute paths only; command lines and usernames cannot be recognized
+ * in prose. A pseudonym is the first 8 hex digits of SHA-256 of the value: stable across reports
+ * so analysts can correlate, but not anonymization (short values can be brute-forced).
+ */
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::net::IpAddr;
+
+/// Audience of a report.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RedactionProfile {
+    /// Incident responders and legal: nothing removed
+    #[default]
+    FullForensic,
+    /// SOC analysts: usernames pseudonymized
+    Soc,
+    /// Management: sensitive fields removed, no per-item evidence details
+    Executive,
+}
+
+/// Sensitive field categories a profile controls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SensitiveField {
+    CommandLine,
+    Username,
+    IpAddress,
+    FilePath,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Treatment {
+    Include,
+    /// Stable pseudonym ("username:1a2b3c4d")
+    Pseudonymize,
+    /// Marker ("[REDACTED:username]")
+    Redact,
+}
+
+impl RedactionProfile {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RedactionProfile::FullForensic => "full_forensic",
+            RedactionProfile::Soc => "soc",
+            RedactionProfile::Executive => "executive",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "full_forensic" => Some(RedactionProfile::FullForensic),
+            "soc" => Some(RedactionProfile::Soc),
+            "executive" => Some(RedactionProfile::Executive),
+            _ => None,
+        }
+    }
+
+    pub fn treatment(&self, field: SensitiveField) -> Treatment {
+        match (self, field) {
+            (RedactionProfile::FullForensic, _) => Treatment::Include,
+            (RedactionProfile::Soc, SensitiveField::Username) => Treatment::Pseudonymize,
+            (RedactionProfile::Soc, _) => Treatment::Include,
+            (RedactionProfile::Executive, _) => Treatment::Redact,
+        }
+    }
+
+    /// Whether reports carry a per-item evidence details section.
+    pub fn includes_evidence_details(&self) -> bool {
+        !matches!(self, RedactionProfile::Executive)
+    }
+}
+
+impl SensitiveField {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SensitiveField::CommandLine => "command_line",
+            SensitiveField::Username => "username",
+            SensitiveField::IpAddress => "ip_address",
+            SensitiveField::FilePath => "file_path",
+        }
+    }
+
+    /// Category of a JSON key, if it holds a sensitive field.
+    pub fn classify_key(key: &str) -> Option<Self> {
+        let key = key.to_ascii_lowercase();
+        let key = key.as_str();
+        let is = |names: &[&str], suffixes: &[&str]| names.contains(&key) || suffixes.iter().any(|s| key.ends_with(s));
+        if is(&["cmdline", "command_line", "commandline", "command", "args", "argv"], &["_cmdline", "_command_line"]) {
+            Some(SensitiveField::CommandLine)
+        } else if is(
+            &["user", "username", "user_name", "account", "account_name", "login", "owner"],
+            &["_user", "_username", "_user_name", "_account"],
+        ) {
+            Some(SensitiveField::Username)
+        } else if is(
+            &["ip", "ip_address", "ip_addr", "remote_addr", "local_addr", "peer_addr"],
+            &["_ip", "_ip_address", "_addr"],
+        ) {
+            Some(SensitiveField::IpAddress)
+        } else if is(
+            &["path", "file_path", "filepath", "file", "filename", "file_name", "executable", "exe", "image", "cwd", "directory"],
+            &["_path", "_file", "_dir"],
+        ) {
+            Some(SensitiveField::FilePath)
+        } else {
+            None
+        }
+    }
+}
+
+static IPV4: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b(?:\d{1,3}\.){3}\d{1,3}\b").unwrap());
+static IPV6: Lazy<Regex> = Lazy::new(|| Regex::new(r"[0-9A-Fa-f]{0,4}(?::[0-9A-Fa-f]{0,4}){2,7}").unwrap());
+/// Absolute Unix path after start/whitespace/quote/'='/'(' (group 1 is that prefix)
+static UNIX_PATH: Lazy<Regex> = Lazy::new(|| Regex::new(r#"(^|[\s"'=(])(/[^\s"',;)]+)"#).unwrap());
+static WINDOWS_PATH: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\b[A-Za-z]:\\[^\s"',;)]*"#).unwrap());
+
+/// Applies one profile to evidence-derived content.
+#[derive(Debug, Clone, Copy)]
+pub struct Redactor {
+    profile: RedactionProfile,
+}
+
+impl Redactor {
+    pub fn new(profile: RedactionProfile) -> Self {
+        Self { profile }
+    }
+
+    pub fn profile(&self) -> RedactionProfile {
+        self.profile
+    }
+
+    /// Redact a JSON document: values under sensitive keys by category, other strings as free text.
+    pub fn redact_value(&self, value: &Value) -> Value {
+        match value {
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(key, v)| {
+                        let redacted = match SensitiveField::classify_key(key) {
+                            Some(field) => self.redact_field(field, v),
+                            None => self.redact_value(v),
+                        };
+                        (key.clone(), redacted)
+                    })
+                    .collect(),
+            ),
+            Value::Array(items) => Value::Array(items.iter().map(|v| self.redact_value(v)).collect()),
+            Value::String(s) => Value::String(self.redact_text(s)),
+            other => other.clone(),
+        }
+    }
+
+    /// Redact IP addresses and absolute paths in free text.
+    pub fn redact_text(&self, text: &str) -> String {
+        let mut out = text.to_string();
+        let ip = self.profile.treatment(SensitiveField::IpAddress);
+        if ip != Treatment::Include {
+            for re in [&*IPV4, &*IPV6] {
+                out = re
+                    .replace_all(&out, |c: &Captures| {
+                        let matched = &c[0];
+                        // "::" alone or "d::" (as in std::net) parse as IPv6 but are not addresses
+                        let groups = matched.split(|ch| ch == ':' || ch == '.').filter(|g| !g.is_empty()).count();
+                        match matched.parse::<IpAddr>() {
+                            Ok(_) if groups >= 2 => self.apply(SensitiveField::IpAddress, ip, matched),
+                            _ => matched.to_string(),
+                        }
+                    })
+                    .into_owned();
+            }
+        }
+        let path = self.profile.treatment(SensitiveField::FilePath);
+        if path != Treatment::Include {
+            out = UNIX_PATH
+                .replace_all(&out, |c: &Captures| format!("{}{}", &c[1], self.apply(SensitiveField::FilePath, path, &c[2])))
+                .into_owned();
+            out = WINDOWS_PATH
+                .replace_all(&out, |c: &Captures| self.apply(SensitiveField::FilePath, path, &c[0]))
+                .into_owned();
+        }
+        out
+    }
+
+    fn redact_field(&self, field: SensitiveField, value: &Value) -> Value {
+        let treatment = self.profile.treatment(field);
+        match (treatment, value) {
+            (Treatment::Include, v) => self.redact_value(v),
+            (_, Value::Null) => Value::Null,
+            (_, Value::String(s)) => Value::String(self.apply(field, treatment, s)),
+            // argv arrays, structured user objects: the whole value is one sensitive field
+            (_, v) => Value::String(self.apply(field, treatment, &v.to_string())),
+        }
+    }
+
+    fn apply(&self, field: SensitiveField, treatment: Treatment, value: &str) -> String {
+        match treatment {
+            Treatment::Include => value.to_string(),
+            Treatment::Pseudonymize => {
+                let digest = Sha256::digest(value.as_bytes());
+                format!("{}:{}", field.as_str(), &hex::encode(digest)[..8])
+            }
+            Treatment::Redact => format!("[REDACTED:{}]", field.as_str()),
+        }
+    }
+}
@@ -9,8 +9,13 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
 
+use crate::annotations::{Annotation, Annotations};
+use crate::collector::CollectedEvidence;
 use crate::errors::ReportingError;
 use crate::evidence_store::EvidenceBundle;
+use crate::pcap_import::{PCAP_DATA_KEY, PCAP_SOURCE_TYPE};
+use crate::sandbox_import::{REPORT_DATA_KEY, SANDBOX_SOURCE_TYPE};
+use crate::redaction::{RedactionProfile, Redactor};
 use crate::timeline::ForensicTimeline;
 
 /// Report metadata - version information and build hashes
@@ -22,6 +27,9 @@ pub struct ReportMetadata {
     pub policy_version: String,
     pub build_hash: String,
     pub model_version_hash: Option<String>,
+    /// Audience profile applied to evidence-derived content (absent before profiles: full_forensic)
+    #[serde(default)]
+    pub redaction_profile: RedactionProfile,
 }
 
 /// Forensic report - complete report with evidence references
@@ -36,6 +44,9 @@ pub struct ForensicReport {
     pub sections: Vec<ReportSection>,
     pub evidence_hashes: Vec<String>,
     pub reproducible: bool,
+    /// Analyst notes linked to the evidence, each with its full revision history
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,6 +56,58 @@ pub struct ReportSummary {
     pub time_range_end: Option<DateTime<Utc>>,
     pub kill_chain_stages: Vec<String>,
     pub sources: Vec<String>,
+    /// Residency classification of the included data (absent in reports built before residency)
+    #[serde(default)]
+    pub residency: ResidencyClassification,
+}
+
+/// Evidence metadata key (or top-level data field, as on exported raw_events rows) carrying the
+/// residency region of the evidence's data, as recorded by ingest in raw_events.residency_region.
+pub const RESIDENCY_METADATA_KEY: &str = "residency_region";
+
+/// Data residency regions a report's evidence belongs to.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResidencyClassification {
+    /// Distinct regions, sorted
+    pub regions: Vec<String>,
+    /// Evidence items carrying no region
+    pub unclassified_items: usize,
+}
+
+impl ResidencyClassification {
+    pub fn classify<'a>(items: impl IntoIterator<Item = &'a CollectedEvidence>) -> Self {
+        let mut regions = std::collections::BTreeSet::new();
+        let mut unclassified_items = 0;
+        for item in items {
+            let region = item
+                .metadata
+                .get(RESIDENCY_METADATA_KEY)
+                .map(String::as_str)
+                .or_else(|| item.data.get(RESIDENCY_METADATA_KEY).and_then(Value::as_str))
+                .filter(|r| !r.is_empty());
+            match region {
+                Some(region) => {
+                    regions.insert(region.to_string());
+                }
+                None => unclassified_items += 1,
+            }
+        }
+        Self { regions: regions.into_iter().collect(), unclassified_items }
+    }
+
+    /// Single-line marking for exports: "eu-west", "multi-region: eu-west, us-east",
+    /// "unclassified", with "+ N unclassified item(s)" when only part of the data carries a region.
+    pub fn label(&self) -> String {
+        let mut label = match self.regions.as_slice() {
+            [] => return "unclassified".to_string(),
+            [region] => region.clone(),
+            regions => format!("multi-region: {}", regions.join(", ")),
+        };
+        if self.unclassified_items > 0 {
+            label.push_str(&format!(" + {} unclassified item(s)", self.unclassified_items));
+        }
+        label
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -56,11 +119,14 @@ pub struct ReportSection {
 }
 
 /// Report builder - constructs reproducible reports
+#[derive(Debug, Clone)]
 pub struct ReportBuilder {
     engine_version: String,
     policy_version: String,
     build_hash: String,
     model_version_hash: Option<String>,
+    redaction: RedactionProfile,
+    annotations: Annotations,
 }
 
 impl ReportBuilder {
@@ -75,8 +141,22 @@ impl ReportBuilder {
             policy_version: policy_version.to_string(),
             build_hash: build_hash.to_string(),
             model_version_hash: model_version_hash.map(|s| s.to_string()),
+            redaction: RedactionProfile::default(),
+            annotations: Annotations::new(),
         }
     }
+
+    /// Build reports for this audience (see redaction.rs); full_forensic by default.
+    pub fn with_redaction(mut self, profile: RedactionProfile) -> Self {
+        self.redaction = profile;
+        self
+    }
+
+    /// Include the notes of this export that are linked to a report's evidence (see annotations.rs).
+    pub fn with_annotations(mut self, annotations: Annotations) -> Self {
+        self.annotations = annotations;
+        self
+    }
     
     /// Build report from evidence bundles
     pub fn build_report(
@@ -88,6 +168,11 @@ impl ReportBuilder {
     ) -> Result<ForensicReport, ReportingError> {
         let report_id = uuid::Uuid::new_v4().to_string();
         let created_at = Utc::now();
+        let redactor = Redactor::new(self.redaction);
+        let timeline = timeline.map(|mut t| {
+            t.redact(&redactor);
+            t
+        });
         
         // Collect evidence bundle IDs and hashes
         let evidence_bundle_ids: Vec<String> = bundles.iter()
@@ -128,10 +213,28 @@ impl ReportBuilder {
             time_range_end,
             kill_chain_stages: kill_chain_stages.into_iter().collect(),
             sources: sources.into_iter().collect(),
+            residency: ResidencyClassification::classify(bundles.iter().flat_map(|b| &b.evidence_items)),
         };
         
+        // Analyst notes are free text: same IP/path redaction as timeline descriptions
+        let annotations: Vec<Annotation> = self
+            .annotations
+            .linked(bundles, timeline.as_ref())
+            .into_iter()
+            .map(|mut a| {
+                for revision in &mut a.revisions {
+                    revision.body = redactor.redact_text(&revision.body);
+                    revision.edit_reason = revision.edit_reason.as_deref().map(|r| redactor.redact_text(r));
+                }
+                a
+            })
+            .collect();
+
         // Build sections
-        let sections = self.build_sections(bundles, &timeline)?;
+        let mut sections = self.build_sections(bundles, &timeline, &redactor)?;
+        if !annotations.is_empty() {
+            sections.push(Self::annotation_section(&annotations));
+        }
         
         let metadata = ReportMetadata {
             report_id: report_id.clone(),
@@ -140,6 +243,7 @@ impl ReportBuilder {
             policy_version: self.policy_version.clone(),
             build_hash: self.build_hash.clone(),
             model_version_hash: self.model_version_hash.clone(),
+            redaction_profile: self.redaction,
         };
         
         Ok(ForensicReport {
@@ -152,14 +256,58 @@ impl ReportBuilder {
             sections,
             evidence_hashes,
             reproducible: true,
+            annotations,
         })
     }
+
+    /// One subsection per note: current text, then the revisions it replaced.
+    fn annotation_section(annotations: &[Annotation]) -> ReportSection {
+        let subsections = annotations
+            .iter()
+            .map(|a| {
+                let current = a.current();
+                let mut content = format!(
+                    "{} (revision {} by {} at {})",
+                    current.body,
+                    current.revision,
+                    current.author,
+                    current.edited_at.to_rfc3339()
+                );
+                for earlier in a.revisions.iter().rev().skip(1) {
+                    content.push_str(&format!(
+                        " | revision {} by {} at {}: {}",
+                        earlier.revision,
+                        earlier.author,
+                        earlier.edited_at.to_rfc3339(),
+                        earlier.body
+                    ));
+                }
+                ReportSection {
+                    title: format!("Note on {} {}", a.subject_type.as_str(), a.subject_id),
+                    content,
+                    evidence_references: vec![a.annotation_id.clone()],
+                    subsections: Vec::new(),
+                }
+            })
+            .collect();
+        ReportSection {
+            title: "Analyst Annotations".to_string(),
+            content: format!(
+                "{} note(s) with {} revision(s); earlier revisions are preserved.",
+                annotations.len(),
+                annotations.iter().map(|a| a.revisions.len()).sum::<usize>()
+            ),
+            evidence_references: annotations.iter().map(|a| a.annotation_id.clone()).collect(),
+            subsections,
+        }
+    }
     
     /// Build report sections
     fn build_sections(
         &self,
         bundles: &[EvidenceBundle],
         timeline: &Option<ForensicTimeline>,
+        redactor: &Redactor,
     ) -> Result<Vec<ReportSection>, ReportingError> {
         let mut sections = Vec::new();
         
@@ -217,6 +365,44 @@ impl ReportBuilder {
             evidence_references: bundles.iter().map(|b| b.bundle_id.clone()).collect(),
             subsections: bundle_subsections,
         });
+
+        // Evidence Details Section (redacted for the audience; omitted for executives)
+        if redactor.profile().includes_evidence_details() {
+            let mut item_subsections = Vec::new();
+            for evidence in bundles.iter().flat_map(|b| &b.evidence_items) {
+                // A capture's pcap and a sandbox report stay in the evidence store (export-capture,
+                // export-sandbox-report), not in the report
+                let mut data = evidence.data.clone();
+                let raw_key = match evidence.source_type.as_str() {
+                    PCAP_SOURCE_TYPE => Some(PCAP_DATA_KEY),
+                    SANDBOX_SOURCE_TYPE => Some(REPORT_DATA_KEY),
+                    _ => None,
+                };
+                if let (Some(key), Some(fields)) = (raw_key, data.as_object_mut()) {
+                    fields.remove(key);
+                }
+                let details = serde_json::json!({
+                    "data": redactor.redact_value(&data),
+                    "metadata": redactor.redact_value(&serde_json::to_value(&evidence.metadata)?),
+                });
+                item_subsections.push(ReportSection {
+                    title: format!("{} from {} at {}", evidence.source_type, evidence.source, evidence.timestamp.to_rfc3339()),
+                    content: serde_json::to_string(&details)?,
+                    evidence_references: vec![evidence.evidence_id.clone()],
+                    subsections: Vec::new(),
+                });
+            }
+            sections.push(ReportSection {
+                title: "Evidence Details".to_string(),
+                content: format!(
+                    "{} evidence items (redaction profile: {})",
+                    item_subsections.len(),
+                    redactor.profile().as_str()
+                ),
+                evidence_references: Vec::new(),
+                subsections: item_subsections,
+            });
+        }
         
         Ok(sections)
     }
@@ -1,10 +1,11 @@
 // Path and File Name : /home/ransomeye/rebuild/ransomeye_reporting/src/retention.rs
 // Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
-// Details of functionality of this file: Retention manager - enforces retention policies, secure deletion, and purge event logging
+// Details of functionality of this file: Retention manager - enforces retention policies, legal-hold exemption, secure deletion, and purge event logging
 
 #![cfg(feature = "future-retention")]
 
 use chrono::{DateTime, Utc, Duration};
+use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::io::Write;
@@ -12,7 +13,7 @@ use serde::{Deserialize, Serialize};
 use tracing::{debug, error, warn};
 
 use crate::errors::ReportingError;
-use crate::evidence_store::EvidenceStore;
+use crate::evidence_store::{EvidenceBundle, EvidenceStore};
 use crate::hasher::EvidenceHasher;
 
 /// Retention policy configuration
@@ -43,6 +44,105 @@ pub struct PurgeEvent {
     pub retention_days: i64,
     pub cutoff_date: DateTime<Utc>,
     pub destruction_certificate_path: Option<String>,
+    /// Bundles past the cutoff kept because they are linked to a held incident or entity
+    #[serde(default)]
+    pub legal_hold_skipped: usize,
+    #[serde(default)]
+    pub legal_hold_bundle_ids: Vec<String>,
+}
+
+/// Active legal holds, as exported from the control plane (GET /admin/legal-holds).
+///
+/// A bundle is held when any evidence item names a held subject in its metadata or in a
+/// top-level string field of its data: incident_id for incidents, entity_id or *_entity_id
+/// for entities - the same column rule the database retention enforcer applies.
+#[derive(Debug, Clone, Default)]
+pub struct LegalHolds {
+    incidents: HashSet<String>,
+    entities: HashSet<String>,
+}
+
+impl LegalHolds {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn hold_incident(&mut self, incident_id: &str) {
+        self.incidents.insert(incident_id.trim().to_lowercase());
+    }
+
+    pub fn hold_entity(&mut self, entity_id: &str) {
+        self.entities.insert(entity_id.trim().to_lowercase());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.incidents.is_empty() && self.entities.is_empty()
+    }
+
+    /// Load a hold export: the list page ({"data": [...]}) or a bare array of holds.
+    /// Released holds (active = false) are ignored; an unknown subject_type fails closed.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ReportingError> {
+        let path = path.as_ref();
+        let doc: serde_json::Value = serde_json::from_str(&fs::read_to_string(path)?)?;
+        let items = doc
+            .get("data")
+            .unwrap_or(&doc)
+            .as_array()
+            .ok_or_else(|| ReportingError::InvalidConfiguration(format!(
+                "Legal hold export {} is not a list of holds", path.display()
+            )))?;
+
+        let mut holds = Self::new();
+        for item in items {
+            if item.get("active").and_then(|v| v.as_bool()) == Some(false) {
+                continue;
+            }
+            let subject_id = item.get("subject_id").and_then(|v| v.as_str());
+            match (item.get("subject_type").and_then(|v| v.as_str()), subject_id) {
+                (Some("incident"), Some(id)) => holds.hold_incident(id),
+                (Some("entity"), Some(id)) => holds.hold_entity(id),
+                _ => {
+                    return Err(ReportingError::InvalidConfiguration(format!(
+                        "Legal hold export {} has an entry without a valid subject_type/subject_id: {}",
+                        path.display(), item
+                    )));
+                }
+            }
+        }
+        Ok(holds)
+    }
+
+    /// True if any evidence item in the bundle is linked to a held subject
+    pub fn holds_bundle(&self, bundle: &EvidenceBundle) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+        bundle.evidence_items.iter().any(|evidence| {
+            let data_fields = evidence
+                .data
+                .as_object()
+                .into_iter()
+                .flat_map(|o| o.iter())
+                .filter_map(|(k, v)| v.as_str().map(|v| (k.as_str(), v)));
+            evidence
+                .metadata
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.as_str()))
+                .chain(data_fields)
+                .any(|(key, value)| self.holds_link(key, value))
+        })
+    }
+
+    fn holds_link(&self, key: &str, value: &str) -> bool {
+        let value = value.trim().to_lowercase();
+        if key == "incident_id" {
+            self.incidents.contains(&value)
+        } else if key == "entity_id" || key.ends_with("_entity_id") {
+            self.entities.contains(&value)
+        } else {
+            false
+        }
+    }
 }
 
 /// Retention manager - enforces retention policies
@@ -51,6 +151,7 @@ pub struct RetentionManager {
     store: EvidenceStore,
     hasher: EvidenceHasher,
     ledger_path: PathBuf,
+    legal_holds: LegalHolds,
 }
 
 impl RetentionManager {
@@ -72,8 +173,15 @@ impl RetentionManager {
             store,
             hasher: EvidenceHasher::new(),
             ledger_path,
+            legal_holds: LegalHolds::new(),
         })
     }
+
+    /// Exempt bundles linked to held incidents/entities from purging
+    pub fn with_legal_holds(mut self, legal_holds: LegalHolds) -> Self {
+        self.legal_holds = legal_holds;
+        self
+    }
     
     /// Enforce retention policy
     /// Purges evidence bundles older than retention period
@@ -82,9 +190,16 @@ impl RetentionManager {
         let bundles = self.store.get_all_bundles();
         
         let mut purged_bundles = Vec::new();
+        let mut held_bundles = Vec::new();
         
         for bundle in &bundles {
             if bundle.created_at < cutoff_date {
+                if self.legal_holds.holds_bundle(bundle) {
+                    warn!("Skipping bundle {} - linked to an incident or entity under legal hold", bundle.bundle_id);
+                    held_bundles.push(bundle.bundle_id.clone());
+                    continue;
+                }
+
                 // Check if bundle contains AI artifacts (protected)
                 let contains_ai_artifacts = self.contains_ai_artifacts(bundle)?;
                 
@@ -107,9 +222,9 @@ impl RetentionManager {
             }
         }
         
-        if !dry_run && !purged_bundles.is_empty() {
+        if !dry_run && !(purged_bundles.is_empty() && held_bundles.is_empty()) {
             // Create destruction certificate
-            let cert_path = self.create_destruction_certificate(&purged_bundles, cutoff_date)?;
+            let cert_path = self.create_destruction_certificate(&purged_bundles, &held_bundles, cutoff_date)?;
             
             // Log purge event
             self.log_purge_event(PurgeEvent {
@@ -120,6 +235,8 @@ impl RetentionManager {
                 retention_days: self.policy.forensic_retention_days,
                 cutoff_date,
                 destruction_certificate_path: Some(cert_path.to_string_lossy().to_string()),
+                legal_hold_skipped: held_bundles.len(),
+                legal_hold_bundle_ids: held_bundles,
             })?;
         }
         
@@ -127,7 +244,7 @@ impl RetentionManager {
     }
     
     /// Check if bundle contains AI artifacts
-    fn contains_ai_artifacts(&self, bundle: &crate::evidence_store::EvidenceBundle) -> Result<bool, ReportingError> {
+    fn contains_ai_artifacts(&self, bundle: &EvidenceBundle) -> Result<bool, ReportingError> {
         // Check metadata for AI artifact indicators
         for evidence in &bundle.evidence_items {
             if evidence.source_type.contains("ai") || 
@@ -157,6 +274,7 @@ impl RetentionManager {
     fn create_destruction_certificate(
         &self,
         bundle_ids: &[String],
+        held_bundle_ids: &[String],
         cutoff_date: DateTime<Utc>,
     ) -> Result<PathBuf, ReportingError> {
         let cert_dir = self.ledger_path.parent()
@@ -179,6 +297,8 @@ impl RetentionManager {
             "retention_days": self.policy.forensic_retention_days,
             "cutoff_date": cutoff_date.to_rfc3339(),
             "total_bundles": bundle_ids.len(),
+            "legal_hold_skipped": held_bundle_ids.len(),
+            "legal_hold_bundle_ids": held_bundle_ids,
         });
         
         let cert_json = serde_json::to_string_pretty(&certificate)
@@ -204,7 +324,7 @@ impl RetentionManager {
         writeln!(file, "{}", event_json)
             .map_err(|e| ReportingError::IoError(e))?;
         
-        debug!("Logged purge event: {} bundles", event.bundle_ids.len());
+        debug!("Logged purge event: {} bundles ({} kept under legal hold)", event.bundle_ids.len(), event.legal_hold_skipped);
         Ok(())
     }
     
@@ -0,0 +1,245 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_reporting/src/sandbox_import.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Sandbox report import - seals detonation reports spooled by ingest into the evidence store, linked to their detection, agent and incident, and reads them back with their hash verified
+
+#![cfg(feature = "future-reporting")]
+
+/*
+ * Sandbox Report Import
+ *
+ * Ingest spools the report of every sandbox verdict as <submission_id>.report with a manifest
+ * <submission_id>.json (see core/ingest/src/sandbox.rs). `import-sandbox-reports` seals every
+ * spooled report into its own evidence bundle:
+ *
+ *   source / source_type   sandbox / sandbox_report
+ *   timestamp              reported_at (when the verdict was recorded)
+ *   data                   the manifest and the report itself (report_base64)
+ *   metadata               submission_id, sha256, verdict, detection_id, agent_id, incident_id
+ *
+ * The report is only taken when its size and SHA-256 match the manifest; a report that does
+ * not is moved to <spool>/rejected/ and nothing of it is sealed. Imported reports are removed
+ * from the spool once their bundle is sealed. Samples awaiting submission (<spool>/samples/)
+ * are never imported.
+ */
+
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::collector::{CollectedEvidence, EvidenceCollector};
+use crate::errors::ReportingError;
+use crate::evidence_store::EvidenceStore;
+use crate::hasher::EvidenceHasher;
+
+/// Evidence source of imported reports
+pub const SANDBOX_SOURCE: &str = "sandbox";
+/// Evidence source_type of imported reports
+pub const SANDBOX_SOURCE_TYPE: &str = "sandbox_report";
+/// Key of the base64 report in the evidence data (left out of report evidence details)
+pub const REPORT_DATA_KEY: &str = "report_base64";
+/// Spool subdirectory for reports that failed verification
+pub const REJECTED_DIR: &str = "rejected";
+
+/// Manifest written by ingest next to a spooled sandbox report.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SandboxManifest {
+    pub submission_id: Uuid,
+    pub sha256: String,
+    pub file_path: Option<String>,
+    pub file_size: Option<i64>,
+    pub agent_id: Option<Uuid>,
+    pub incident_id: Option<Uuid>,
+    pub source: String,
+    pub submitted_by: String,
+    pub mode: String,
+    pub sandbox_url: String,
+    pub sandbox_task_id: i64,
+    pub queued_at: DateTime<Utc>,
+    pub reported_at: DateTime<Utc>,
+    pub score: f64,
+    pub verdict: String,
+    pub detection_id: Option<Uuid>,
+    pub report_file: String,
+    pub report_sha256: String,
+    pub report_bytes: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportedReport {
+    pub submission_id: Uuid,
+    pub bundle_id: String,
+    pub evidence_id: String,
+    pub verdict: String,
+    pub report_sha256: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct RejectedReport {
+    /// Manifest file name (the submission id when the manifest could not be read)
+    pub manifest: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ImportOutcome {
+    pub imported: Vec<ImportedReport>,
+    pub rejected: Vec<RejectedReport>,
+}
+
+/// Manifest and report of one spooled verdict, verified against each other.
+fn load_report(spool_dir: &Path, manifest_path: &Path) -> Result<(SandboxManifest, Vec<u8>), String> {
+    let manifest: SandboxManifest = serde_json::from_slice(&fs::read(manifest_path).map_err(|e| e.to_string())?)
+        .map_err(|e| format!("unreadable manifest: {}", e))?;
+    if manifest_path.file_stem().and_then(|s| s.to_str()) != Some(manifest.submission_id.to_string().as_str()) {
+        return Err(format!("manifest names submission {}", manifest.submission_id));
+    }
+    // The report must sit next to its manifest under its own name
+    if manifest.report_file != format!("{}.report", manifest.submission_id) {
+        return Err(format!("unexpected report file name '{}'", manifest.report_file));
+    }
+    let report =
+        fs::read(spool_dir.join(&manifest.report_file)).map_err(|e| format!("report not readable: {}", e))?;
+    if report.len() as u64 != manifest.report_bytes {
+        return Err(format!("report is {} bytes, manifest says {}", report.len(), manifest.report_bytes));
+    }
+    let sha256 = EvidenceHasher::new().hash_bytes(&report);
+    if !sha256.eq_ignore_ascii_case(&manifest.report_sha256) {
+        return Err(format!("report SHA-256 {} does not match manifest {}", sha256, manifest.report_sha256));
+    }
+    Ok((manifest, report))
+}
+
+fn evidence_for(
+    collector: &EvidenceCollector,
+    manifest: &SandboxManifest,
+    report: &[u8],
+) -> Result<CollectedEvidence, ReportingError> {
+    let mut data = serde_json::to_value(manifest)?;
+    data[REPORT_DATA_KEY] = serde_json::Value::String(general_purpose::STANDARD.encode(report));
+    let mut metadata = HashMap::from([
+        ("submission_id".to_string(), manifest.submission_id.to_string()),
+        ("sha256".to_string(), manifest.sha256.clone()),
+        ("verdict".to_string(), manifest.verdict.clone()),
+    ]);
+    if let Some(id) = manifest.detection_id {
+        metadata.insert("detection_id".to_string(), id.to_string());
+    }
+    if let Some(id) = manifest.agent_id {
+        metadata.insert("agent_id".to_string(), id.to_string());
+    }
+    if let Some(id) = manifest.incident_id {
+        metadata.insert("incident_id".to_string(), id.to_string());
+    }
+    collector.collect_with_timestamp(SANDBOX_SOURCE, SANDBOX_SOURCE_TYPE, data, manifest.reported_at, None, metadata)
+}
+
+fn reject(spool_dir: &Path, manifest_path: &Path, reason: String) -> Result<RejectedReport, ReportingError> {
+    let rejected_dir = spool_dir.join(REJECTED_DIR);
+    fs::create_dir_all(&rejected_dir)?;
+    let stem = manifest_path.file_stem().and_then(|s| s.to_str()).unwrap_or_default().to_string();
+    for path in [manifest_path.to_path_buf(), spool_dir.join(format!("{}.report", stem))] {
+        if let Some(name) = path.file_name() {
+            if path.exists() {
+                fs::rename(&path, rejected_dir.join(name))?;
+            }
+        }
+    }
+    warn!("Rejected spooled sandbox report {}: {}", stem, reason);
+    Ok(RejectedReport { manifest: stem, reason })
+}
+
+/// Seal every spooled sandbox report into the evidence store (one bundle per report).
+/// Store errors abort the import; the report being imported stays in the spool.
+pub fn import_reports(
+    store: &EvidenceStore,
+    collector: &EvidenceCollector,
+    engine_version: &str,
+    policy_version: &str,
+    spool_dir: &Path,
+) -> Result<ImportOutcome, ReportingError> {
+    let mut manifests: Vec<PathBuf> = fs::read_dir(spool_dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && p.extension().and_then(|e| e.to_str()) == Some("json"))
+        .collect();
+    manifests.sort();
+
+    let mut outcome = ImportOutcome::default();
+    for manifest_path in manifests {
+        let (manifest, report) = match load_report(spool_dir, &manifest_path) {
+            Ok(report) => report,
+            Err(reason) => {
+                outcome.rejected.push(reject(spool_dir, &manifest_path, reason)?);
+                continue;
+            }
+        };
+        let evidence = match evidence_for(collector, &manifest, &report) {
+            Ok(evidence) => evidence,
+            Err(e) => {
+                outcome.rejected.push(reject(spool_dir, &manifest_path, e.to_string())?);
+                continue;
+            }
+        };
+        let evidence_id = evidence.evidence_id.clone();
+        let bundle_id = store.create_bundle(engine_version, policy_version)?;
+        store.add_evidence(&bundle_id, evidence)?;
+        store.seal_bundle(&bundle_id)?;
+        // Sealed: the spool copy is no longer needed
+        fs::remove_file(&manifest_path)?;
+        fs::remove_file(spool_dir.join(&manifest.report_file))?;
+        info!(
+            "Imported sandbox report {} | bundle={} | sha256={} | verdict={} | score={}",
+            manifest.submission_id, bundle_id, manifest.sha256, manifest.verdict, manifest.score
+        );
+        outcome.imported.push(ImportedReport {
+            submission_id: manifest.submission_id,
+            bundle_id,
+            evidence_id,
+            verdict: manifest.verdict,
+            report_sha256: manifest.report_sha256,
+        });
+    }
+    Ok(outcome)
+}
+
+/// The report of an imported verdict, verified against the SHA-256 sealed with it.
+pub fn read_report(evidence: &CollectedEvidence) -> Result<Vec<u8>, ReportingError> {
+    if evidence.source_type != SANDBOX_SOURCE_TYPE {
+        return Err(ReportingError::MissingEvidence(format!(
+            "Evidence {} is {}, not a sandbox report",
+            evidence.evidence_id, evidence.source_type
+        )));
+    }
+    let field = |key: &str| {
+        evidence.data.get(key).and_then(|v| v.as_str()).ok_or_else(|| {
+            ReportingError::EvidenceCorrupted(format!("Sandbox evidence {} has no {}", evidence.evidence_id, key))
+        })
+    };
+    let report = general_purpose::STANDARD
+        .decode(field(REPORT_DATA_KEY)?)
+        .map_err(|e| ReportingError::EvidenceCorrupted(format!("Sandbox evidence {}: {}", evidence.evidence_id, e)))?;
+    let expected = field("report_sha256")?.to_ascii_lowercase();
+    let actual = EvidenceHasher::new().hash_bytes(&report);
+    if actual != expected {
+        return Err(ReportingError::HashMismatch { expected, actual });
+    }
+    Ok(report)
+}
+
+/// The sealed evidence of a sandbox report, by submission id.
+pub fn find_report(store: &EvidenceStore, submission_id: &str) -> Result<CollectedEvidence, ReportingError> {
+    store
+        .get_all_bundles()
+        .into_iter()
+        .filter(|b| b.is_sealed)
+        .flat_map(|b| b.evidence_items)
+        .find(|e| {
+            e.source_type == SANDBOX_SOURCE_TYPE
+                && e.metadata.get("submission_id").is_some_and(|id| id.eq_ignore_ascii_case(submission_id))
+        })
+        .ok_or_else(|| ReportingError::MissingEvidence(format!("No sealed sandbox report {}", submission_id)))
+}
@@ -0,0 +1,733 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_reporting/src/scheduler.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Report scheduler - generates configured reports (daily executive summary, weekly detection digest) on cron schedules, preserves them in the evidence store and hands them to distribution
+
+#![cfg(feature = "future-reporting")]
+
+/*
+ * Scheduled Reports
+ *
+ * Each configured report has a name, a kind, a 5-field cron expression (UTC) and its recipients.
+ * When a schedule fires, the scheduler:
+ *   1. loads sealed evidence bundles created inside the report window (now - window .. now),
+ *   2. builds the report with ReportBuilder and exports the configured formats,
+ *   3. hashes every artifact (SHA-256) and preserves the report record - report JSON plus the
+ *      artifact hashes - as a sealed bundle in the evidence store (source_type
+ *      "scheduled_report"; these bundles are never fed back into later reports),
+ *   4. distributes the artifacts by email and/or signed webhook with the hashes in the message.
+ *
+ * The last run of every schedule is persisted in <output_dir>/scheduler_state.json. Runs missed
+ * while the scheduler was down collapse into one catch-up run; a schedule seen for the first time
+ * waits for its next fire time. A generation or storage failure leaves the schedule due so the
+ * next tick retries it; distribution failures are logged per recipient and do not regenerate.
+ */
+
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, NaiveDate, TimeZone, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{debug, error, info, warn};
+
+use crate::annotations::Annotations;
+use crate::collector::{CollectedEvidence, EvidenceCollector};
+use crate::distribution::{self, DeliveryReceipt, SmtpConfig, WebhookTarget};
+use crate::download_token::{DownloadConfig, DownloadLink, DownloadSigner};
+use crate::errors::ReportingError;
+use crate::evidence_store::{EvidenceBundle, EvidenceStore, EvidenceStoreOptions};
+use crate::exporter::ReportExporter;
+use crate::hasher::EvidenceHasher;
+use crate::notification_templates::{self, NotificationTemplates, TemplateConfig};
+use crate::redaction::RedactionProfile;
+use crate::report_builder::{ForensicReport, ReportBuilder, ReportSection};
+
+/// source_type of the evidence items the scheduler writes for its own reports
+pub const SCHEDULED_REPORT_SOURCE_TYPE: &str = "scheduled_report";
+const SCHEDULER_SOURCE: &str = "reporting.scheduler";
+const STATE_FILE: &str = "scheduler_state.json";
+
+/// Upper bound for the next-fire search; expressions that never match are rejected at parse time.
+const MAX_SEARCH_DAYS: i64 = 366 * 5;
+
+/// Five-field cron expression (minute hour day-of-month month day-of-week), evaluated in UTC.
+/// Fields accept `*`, values, ranges `a-b`, steps `*/n` / `a-b/n` / `a/n` and comma lists.
+/// Day-of-week is 0-7 (0 and 7 are Sunday). As in Vixie cron, when both day fields are
+/// restricted a day matches if either does. `@hourly`, `@daily`, `@midnight`, `@weekly` and
+/// `@monthly` are accepted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    dom_restricted: bool,
+    dow_restricted: bool,
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self, ReportingError> {
+        let expanded = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(invalid_schedule(expression, "expected 5 fields (minute hour day month weekday)"));
+        }
+        let field = |i: usize, min: u32, max: u32| {
+            parse_field(fields[i], min, max).map_err(|e| invalid_schedule(expression, &e))
+        };
+        let mut days_of_week = field(4, 0, 7)?;
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week = (days_of_week & !(1 << 7)) | 1;
+        }
+        let schedule = Self {
+            expression: expression.trim().to_string(),
+            minutes: field(0, 0, 59)?,
+            hours: field(1, 0, 23)?,
+            days_of_month: field(2, 1, 31)?,
+            months: field(3, 1, 12)?,
+            days_of_week,
+            dom_restricted: !fields[2].starts_with('*'),
+            dow_restricted: !fields[4].starts_with('*'),
+        };
+        let probe = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        if schedule.next_after(probe).is_none() {
+            return Err(invalid_schedule(expression, "expression never fires"));
+        }
+        Ok(schedule)
+    }
+
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    /// First fire time strictly after `after` (minute resolution).
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let limit = after + ChronoDuration::days(MAX_SEARCH_DAYS);
+        let mut t = after.with_second(0)?.with_nanosecond(0)? + ChronoDuration::minutes(1);
+        while t <= limit {
+            if !bit(self.months, t.month()) {
+                let (year, month) = if t.month() == 12 { (t.year() + 1, 1) } else { (t.year(), t.month() + 1) };
+                t = midnight(NaiveDate::from_ymd_opt(year, month, 1)?);
+                continue;
+            }
+            if !self.day_matches(&t) {
+                t = midnight(t.date_naive().succ_opt()?);
+                continue;
+            }
+            if !bit(self.hours, t.hour()) {
+                t = t.with_minute(0)? + ChronoDuration::hours(1);
+                continue;
+            }
+            if !bit(self.minutes, t.minute()) {
+                t += ChronoDuration::minutes(1);
+                continue;
+            }
+            return Some(t);
+        }
+        None
+    }
+
+    fn day_matches(&self, t: &DateTime<Utc>) -> bool {
+        let dom = bit(self.days_of_month, t.day());
+        let dow = bit(self.days_of_week, t.weekday().num_days_from_sunday());
+        match (self.dom_restricted, self.dow_restricted) {
+            (true, true) => dom || dow,
+            (true, false) => dom,
+            (false, true) => dow,
+            (false, false) => true,
+        }
+    }
+}
+
+fn bit(mask: u64, value: u32) -> bool {
+    mask & (1u64 << value) != 0
+}
+
+fn midnight(date: NaiveDate) -> DateTime<Utc> {
+    Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).expect("midnight is always valid"))
+}
+
+fn invalid_schedule(expression: &str, reason: &str) -> ReportingError {
+    ReportingError::InvalidSchedule(format!("'{}': {}", expression, reason))
+}
+
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step.parse().map_err(|_| format!("invalid step in '{}'", part))?;
+                if step == 0 {
+                    return Err(format!("step must be > 0 in '{}'", part));
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let value = |v: &str| -> Result<u32, String> {
+            v.parse::<u32>()
+                .ok()
+                .filter(|n| (min..=max).contains(n))
+                .ok_or_else(|| format!("'{}' is out of range {}-{}", v, min, max))
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            r => match r.split_once('-') {
+                Some((a, b)) => (value(a)?, value(b)?),
+                // "a/n" runs from a to the end of the range
+                None if part.contains('/') => (value(r)?, max),
+                None => (value(r)?, value(r)?),
+            },
+        };
+        if start > end {
+            return Err(format!("range '{}' is reversed", range));
+        }
+        let mut v = start;
+        while v <= end {
+            mask |= 1u64 << v;
+            v += step;
+        }
+    }
+    Ok(mask)
+}
+
+/// Report kinds the scheduler knows how to build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportKind {
+    /// All evidence collected in the window, summarised for leadership (default window 24h)
+    ExecutiveSummary,
+    /// Detection evidence only (source_type "detection"), grouped by kill-chain stage (default window 7d)
+    DetectionDigest,
+}
+
+impl ReportKind {
+    pub fn title(&self) -> &'static str {
+        match self {
+            ReportKind::ExecutiveSummary => "Executive Summary",
+            ReportKind::DetectionDigest => "Detection Digest",
+        }
+    }
+
+    pub fn default_window_hours(&self) -> u32 {
+        match self {
+            ReportKind::ExecutiveSummary => 24,
+            ReportKind::DetectionDigest => 24 * 7,
+        }
+    }
+
+    pub fn default_redaction(&self) -> RedactionProfile {
+        match self {
+            ReportKind::ExecutiveSummary => RedactionProfile::Executive,
+            ReportKind::DetectionDigest => RedactionProfile::Soc,
+        }
+    }
+
+    fn includes(&self, evidence: &CollectedEvidence) -> bool {
+        match self {
+            ReportKind::ExecutiveSummary => evidence.source_type != SCHEDULED_REPORT_SOURCE_TYPE,
+            ReportKind::DetectionDigest => evidence.source_type == "detection",
+        }
+    }
+
+    /// Kind-specific section appended after the builder's standard sections.
+    fn section(&self, bundles: &[EvidenceBundle]) -> ReportSection {
+        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+        for evidence in bundles.iter().flat_map(|b| &b.evidence_items) {
+            let key = match self {
+                ReportKind::ExecutiveSummary => evidence.source_type.clone(),
+                ReportKind::DetectionDigest => evidence
+                    .kill_chain_stage
+                    .clone()
+                    .unwrap_or_else(|| "unclassified".to_string()),
+            };
+            *counts.entry(key).or_insert(0) += 1;
+        }
+        let (title, label) = match self {
+            ReportKind::ExecutiveSummary => ("Evidence by Type", "evidence items"),
+            ReportKind::DetectionDigest => ("Detections by Kill-Chain Stage", "detections"),
+        };
+        ReportSection {
+            title: title.to_string(),
+            content: format!("{} {} in the reporting window.", counts.values().sum::<usize>(), label),
+            evidence_references: Vec::new(),
+            subsections: counts
+                .into_iter()
+                .map(|(key, count)| ReportSection {
+                    title: key,
+                    content: format!("{} {}", count, label),
+                    evidence_references: Vec::new(),
+                    subsections: Vec::new(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Pdf,
+    Html,
+    Csv,
+    /// The ForensicReport document itself
+    Json,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Pdf => "pdf",
+            ExportFormat::Html => "html",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+fn default_formats() -> Vec<ExportFormat> {
+    vec![ExportFormat::Pdf, ExportFormat::Json]
+}
+
+/// One scheduled report.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledReportConfig {
+    /// Unique name; also the artifact directory under output_dir
+    pub name: String,
+    pub kind: ReportKind,
+    /// Cron expression (UTC), see CronSchedule
+    pub cron: String,
+    /// Reporting window; defaults to the kind's window
+    #[serde(default)]
+    pub window_hours: Option<u32>,
+    /// Audience redaction profile; defaults to the kind's (executive summary: executive,
+    /// detection digest: soc)
+    #[serde(default)]
+    pub redaction: Option<RedactionProfile>,
+    #[serde(default = "default_formats")]
+    pub formats: Vec<ExportFormat>,
+    /// Email recipients (requires the smtp section)
+    #[serde(default)]
+    pub email: Vec<String>,
+    #[serde(default)]
+    pub webhooks: Vec<WebhookTarget>,
+    /// Tenant whose notification template overrides apply (default scope when absent)
+    #[serde(default)]
+    pub tenant: Option<String>,
+    /// Notification locale; defaults to templates.default_locale
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// Annotation export (GET /admin/annotations) re-read at every run; linked notes are
+    /// included with their revision history
+    #[serde(default)]
+    pub annotations: Option<PathBuf>,
+}
+
+/// Scheduler configuration file (JSON).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchedulerConfig {
+    /// Exported artifacts and scheduler_state.json are written here
+    pub output_dir: PathBuf,
+    #[serde(default)]
+    pub smtp: Option<SmtpConfig>,
+    /// Send expiring download links instead of email attachments
+    #[serde(default)]
+    pub downloads: Option<DownloadConfig>,
+    /// Notification template overrides; built-in English templates when absent
+    #[serde(default)]
+    pub templates: Option<TemplateConfig>,
+    pub reports: Vec<ScheduledReportConfig>,
+}
+
+impl SchedulerConfig {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ReportingError> {
+        let data = fs::read_to_string(path.as_ref())?;
+        let config: SchedulerConfig = serde_json::from_str(&data)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Reject configurations that could only fail at fire time.
+    pub fn validate(&self) -> Result<(), ReportingError> {
+        let invalid = |msg: String| Err(ReportingError::InvalidSchedule(msg));
+        if let Some(downloads) = &self.downloads {
+            downloads.validate()?;
+        }
+        if let Some(templates) = &self.templates {
+            notification_templates::validate_locale(&templates.default_locale)?;
+        }
+        let mut names = std::collections::HashSet::new();
+        for report in &self.reports {
+            if report.name.is_empty()
+                || !report.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return invalid(format!("report name '{}' must be [A-Za-z0-9_-]+", report.name));
+            }
+            if !names.insert(report.name.as_str()) {
+                return invalid(format!("duplicate report name '{}'", report.name));
+            }
+            CronSchedule::parse(&report.cron)?;
+            if report.window_hours == Some(0) {
+                return invalid(format!("report '{}': window_hours must be > 0", report.name));
+            }
+            if report.formats.is_empty() {
+                return invalid(format!("report '{}': formats must not be empty", report.name));
+            }
+            if !report.email.is_empty() && self.smtp.is_none() {
+                return invalid(format!("report '{}' has email recipients but no smtp section", report.name));
+            }
+            for target in &report.webhooks {
+                distribution::validate_webhook_url(&target.url)
+                    .map_err(|e| ReportingError::InvalidSchedule(format!("report '{}': {}", report.name, e)))?;
+            }
+            let in_report = |e: ReportingError| ReportingError::InvalidSchedule(format!("report '{}': {}", report.name, e));
+            if let Some(tenant) = &report.tenant {
+                notification_templates::validate_tenant(tenant).map_err(in_report)?;
+            }
+            if let Some(locale) = &report.locale {
+                notification_templates::validate_locale(locale).map_err(in_report)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One exported file and its SHA-256.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReportArtifact {
+    pub file_name: String,
+    pub sha256: String,
+    pub bytes: u64,
+}
+
+/// Record of one scheduled run - preserved as evidence and sent to every recipient.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneratedReport {
+    pub schedule: String,
+    pub kind: ReportKind,
+    pub title: String,
+    pub report_id: String,
+    pub generated_at: DateTime<Utc>,
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+    pub evidence_items: usize,
+    pub redaction_profile: RedactionProfile,
+    /// Residency classification of the included data (ResidencyClassification::label)
+    pub residency: String,
+    pub artifacts: Vec<ReportArtifact>,
+    /// Evidence bundle preserving this report record
+    pub evidence_bundle_id: String,
+    pub evidence_bundle_hash: String,
+    /// One link per artifact when downloads are configured (artifacts are then not attached)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub downloads: Vec<DownloadLink>,
+    #[serde(skip)]
+    pub directory: PathBuf,
+}
+
+/// Outcome of one fired schedule.
+#[derive(Debug, Clone)]
+pub struct ScheduledRun {
+    pub report: GeneratedReport,
+    pub deliveries: Vec<DeliveryReceipt>,
+}
+
+/// Runs configured reports against an evidence store.
+pub struct ReportScheduler {
+    config: SchedulerConfig,
+    schedules: Vec<CronSchedule>,
+    store_path: PathBuf,
+    signing_key_path: Option<PathBuf>,
+    builder: ReportBuilder,
+    exporter: ReportExporter,
+    hasher: EvidenceHasher,
+    download_signer: Option<DownloadSigner>,
+    templates: NotificationTemplates,
+    /// schedule name -> last run
+    state: HashMap<String, DateTime<Utc>>,
+}
+
+impl ReportScheduler {
+    pub fn new(
+        config: SchedulerConfig,
+        store_path: impl AsRef<Path>,
+        signing_key_path: Option<&Path>,
+        builder: ReportBuilder,
+    ) -> Result<Self, ReportingError> {
+        config.validate()?;
+        let schedules = config
+            .reports
+            .iter()
+            .map(|r| CronSchedule::parse(&r.cron))
+            .collect::<Result<Vec<_>, _>>()?;
+        // Secret missing or too short: fail at startup rather than at the first fire time
+        let download_signer = config
+            .downloads
+            .as_ref()
+            .map(|d| DownloadSigner::from_env(&d.secret_env))
+            .transpose()?;
+        // A template override that does not render fails startup, not the first notification
+        let templates = match &config.templates {
+            Some(t) => {
+                let templates = NotificationTemplates::load(t)?;
+                info!("Loaded {} notification template override(s) from {}", templates.override_count(), t.dir.display());
+                templates
+            }
+            None => NotificationTemplates::builtin(),
+        };
+        fs::create_dir_all(&config.output_dir)?;
+        let state_path = config.output_dir.join(STATE_FILE);
+        let state = if state_path.exists() {
+            serde_json::from_str(&fs::read_to_string(&state_path)?)?
+        } else {
+            HashMap::new()
+        };
+        Ok(Self {
+            config,
+            schedules,
+            store_path: store_path.as_ref().to_path_buf(),
+            signing_key_path: signing_key_path.map(Path::to_path_buf),
+            builder,
+            exporter: ReportExporter::new(),
+            hasher: EvidenceHasher::new(),
+            download_signer,
+            templates,
+            state,
+        })
+    }
+
+    /// Next fire time of a schedule, from its last run (or `now` for a schedule never run).
+    pub fn next_run(&self, name: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let i = self.config.reports.iter().position(|r| r.name == name)?;
+        let last = self.state.get(name).copied().unwrap_or(now);
+        self.schedules[i].next_after(last)
+    }
+
+    /// Fire every schedule due at `now`. A failing schedule is logged and left due; the others
+    /// still run.
+    pub fn run_due(&mut self, now: DateTime<Utc>) -> Vec<ScheduledRun> {
+        let mut runs = Vec::new();
+        for i in 0..self.config.reports.len() {
+            let name = self.config.reports[i].name.clone();
+            let last = match self.state.get(&name) {
+                Some(last) => *last,
+                None => {
+                    // First sighting: wait for the next fire time instead of firing immediately
+                    self.state.insert(name.clone(), now);
+                    self.save_state_logged();
+                    continue;
+                }
+            };
+            match self.schedules[i].next_after(last) {
+                Some(next) if next <= now => {}
+                _ => continue,
+            }
+            match self.run_report(i, now) {
+                Ok(run) => {
+                    self.state.insert(name, now);
+                    self.save_state_logged();
+                    runs.push(run);
+                }
+                Err(e) => error!("FAIL-CLOSED: scheduled report '{}' failed: {}", name, e),
+            }
+        }
+        runs
+    }
+
+    /// Generate, preserve and distribute one configured report immediately (ignores its schedule).
+    pub fn run_now(&mut self, name: &str, now: DateTime<Utc>) -> Result<ScheduledRun, ReportingError> {
+        let i = self
+            .config
+            .reports
+            .iter()
+            .position(|r| r.name == name)
+            .ok_or_else(|| ReportingError::InvalidSchedule(format!("no scheduled report named '{}'", name)))?;
+        let run = self.run_report(i, now)?;
+        self.state.insert(name.to_string(), now);
+        self.save_state()?;
+        Ok(run)
+    }
+
+    /// Blocking loop: fire due schedules every `tick` until the process exits.
+    pub fn run_forever(&mut self, tick: Duration) -> ! {
+        info!(
+            "Report scheduler running {} schedule(s) (tick={}s)",
+            self.config.reports.len(),
+            tick.as_secs()
+        );
+        loop {
+            let runs = self.run_due(Utc::now());
+            for run in &runs {
+                let failed = run.deliveries.iter().filter(|d| !d.delivered).count();
+                info!(
+                    "Scheduled report '{}' generated ({}), {} deliveries, {} failed",
+                    run.report.schedule,
+                    run.report.report_id,
+                    run.deliveries.len(),
+                    failed
+                );
+            }
+            std::thread::sleep(tick);
+        }
+    }
+
+    fn run_report(&self, index: usize, now: DateTime<Utc>) -> Result<ScheduledRun, ReportingError> {
+        let config = &self.config.reports[index];
+        let mut report = self.generate(config, now)?;
+        if let (Some(downloads), Some(signer)) = (&self.config.downloads, &self.download_signer) {
+            report.downloads = signer.links(&report, downloads, now)?;
+        }
+        let deliveries = distribution::distribute(&report, config, self.config.smtp.as_ref(), &self.templates);
+        for receipt in deliveries.iter().filter(|d| !d.delivered) {
+            warn!(
+                "Scheduled report '{}' not delivered to {}: {}",
+                config.name,
+                receipt.target,
+                receipt.error.as_deref().unwrap_or("unknown error")
+            );
+        }
+        Ok(ScheduledRun { report, deliveries })
+    }
+
+    fn generate(&self, config: &ScheduledReportConfig, now: DateTime<Utc>) -> Result<GeneratedReport, ReportingError> {
+        // Reopen per run so bundles sealed by other writers since the last run are visible
+        let store = EvidenceStore::open(&self.store_path, self.signing_key_path.as_deref(), EvidenceStoreOptions::from_env()?)?;
+        let window = config.window_hours.unwrap_or_else(|| config.kind.default_window_hours());
+        let window_start = now - ChronoDuration::hours(i64::from(window));
+
+        let bundles: Vec<EvidenceBundle> = store
+            .get_bundles_in_range(window_start, now)
+            .into_iter()
+            .filter(|b| b.is_sealed)
+            .filter_map(|mut b| {
+                b.evidence_items.retain(|e| config.kind.includes(e));
+                (!b.evidence_items.is_empty()).then_some(b)
+            })
+            .collect();
+
+        let title = format!("{} ({})", config.kind.title(), config.name);
+        let description = format!(
+            "Scheduled {} for {} to {} (UTC).",
+            config.kind.title(),
+            window_start.to_rfc3339(),
+            now.to_rfc3339()
+        );
+        let redaction = config.redaction.unwrap_or_else(|| config.kind.default_redaction());
+        let mut builder = self.builder.clone().with_redaction(redaction);
+        if let Some(path) = &config.annotations {
+            builder = builder.with_annotations(Annotations::load(path)?);
+        }
+        let mut report = builder.build_report(&title, &description, &bundles, None)?;
+        report.sections.push(config.kind.section(&bundles));
+        let evidence_items = report.summary.total_evidence_items;
+        let residency = report.summary.residency.label();
+
+        let directory = self.config.output_dir.join(&config.name).join(&report.metadata.report_id);
+        fs::create_dir_all(&directory)?;
+        let mut artifacts = Vec::with_capacity(config.formats.len());
+        for format in &config.formats {
+            let file_name = format!("{}_report.{}", report.metadata.report_id, format.extension());
+            let path = directory.join(&file_name);
+            self.export(&report, *format, &path)?;
+            let data = fs::read(&path)?;
+            artifacts.push(ReportArtifact {
+                file_name,
+                sha256: self.hasher.hash_bytes(&data),
+                bytes: data.len() as u64,
+            });
+        }
+
+        let (evidence_bundle_id, evidence_bundle_hash) = self.preserve(&store, config, &report, &artifacts, window_start, now)?;
+        debug!(
+            "Scheduled report '{}' ({}) preserved in bundle {}",
+            config.name, report.metadata.report_id, evidence_bundle_id
+        );
+
+        Ok(GeneratedReport {
+            schedule: config.name.clone(),
+            kind: config.kind,
+            title,
+            report_id: report.metadata.report_id.clone(),
+            generated_at: report.metadata.created_at,
+            window_start,
+            window_end: now,
+            evidence_items,
+            redaction_profile: redaction,
+            residency,
+            artifacts,
+            evidence_bundle_id,
+            evidence_bundle_hash,
+            downloads: Vec::new(),
+            directory,
+        })
+    }
+
+    fn export(&self, report: &ForensicReport, format: ExportFormat, path: &Path) -> Result<(), ReportingError> {
+        match format {
+            ExportFormat::Pdf => self.exporter.export_pdf(report, path),
+            ExportFormat::Html => self.exporter.export_html(report, path),
+            ExportFormat::Csv => self.exporter.export_csv(report, path),
+            ExportFormat::Json => Ok(fs::write(path, serde_json::to_vec_pretty(report)?)?),
+        }
+    }
+
+    /// Seal the report record (report document + artifact hashes) into its own bundle.
+    fn preserve(
+        &self,
+        store: &EvidenceStore,
+        config: &ScheduledReportConfig,
+        report: &ForensicReport,
+        artifacts: &[ReportArtifact],
+        window_start: DateTime<Utc>,
+        window_end: DateTime<Utc>,
+    ) -> Result<(String, String), ReportingError> {
+        let metadata = &report.metadata;
+        let collector = EvidenceCollector::new(&metadata.engine_version, &metadata.policy_version);
+        let evidence = collector.collect(
+            SCHEDULER_SOURCE,
+            SCHEDULED_REPORT_SOURCE_TYPE,
+            serde_json::json!({
+                "schedule": config.name,
+                "kind": config.kind,
+                "cron": config.cron,
+                "window_start": window_start,
+                "window_end": window_end,
+                "artifacts": artifacts,
+                "report": report,
+            }),
+            None,
+            HashMap::from([
+                ("schedule".to_string(), config.name.clone()),
+                ("report_id".to_string(), metadata.report_id.clone()),
+                ("residency_classification".to_string(), report.summary.residency.label()),
+            ]),
+        )?;
+        let bundle_id = store.create_bundle(&metadata.engine_version, &metadata.policy_version)?;
+        store.add_evidence(&bundle_id, evidence)?;
+        store.seal_bundle(&bundle_id)?;
+        let bundle = store.get_bundle(&bundle_id)?;
+        Ok((bundle_id, bundle.bundle_hash))
+    }
+
+    fn save_state(&self) -> Result<(), ReportingError> {
+        let path = self.config.output_dir.join(STATE_FILE);
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(&self.state)?)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    fn save_state_logged(&self) {
+        if let Err(e) = self.save_state() {
+            error!("Failed to persist report scheduler state: {}", e);
+        }
+    }
+}
@@ -109,6 +109,15 @@ impl ForensicTimeline {
         Ok(())
     }
     
+    /// Apply a redaction profile to event descriptions and metadata (report copies only).
+    #[cfg(feature = "future-reporting")]
+    pub fn redact(&mut self, redactor: &crate::redaction::Redactor) {
+        for event in &mut self.events {
+            event.description = redactor.redact_text(&event.description);
+            event.metadata = redactor.redact_value(&event.metadata);
+        }
+    }
+
     /// Get all events (chronologically ordered)
     pub fn get_events(&self) -> &[TimelineEvent] {
         &self.events
@@ -0,0 +1,306 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_reporting/src/worm.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: WORM enforcement for sealed evidence - filesystem immutability flag (chattr +i equivalent) on finalized bundle and payload files, and the audited privileged release workflow
+
+#![cfg(feature = "future-reporting")]
+
+/*
+ * WORM (Write Once, Read Many) Evidence
+ *
+ * With WORM enabled the evidence store sets the filesystem immutable flag (FS_IMMUTABLE_FL, what
+ * `chattr +i` sets) on every finalized file: the sealed bundle file and each payload blob it
+ * references. Immutable files cannot be written, renamed, linked or deleted - not even by root -
+ * until the flag is cleared, which requires CAP_LINUX_IMMUTABLE. The store probes support when
+ * it opens and fails closed if the flag cannot be set (unsupported filesystem or missing
+ * capability), so WORM is never silently degraded.
+ *
+ * Releasing a bundle (before a court-ordered deletion or a retention purge after a legal hold
+ * ends) is an explicit privileged operation:
+ *   - the request names the bundle and its bundle_hash (no releasing the wrong evidence),
+ *     an actor, a different approver (two-person rule) and a reason,
+ *   - a WORM_RELEASE_AUTHORIZED record is written to the signed, hash-chained audit log
+ *     before any flag is cleared; if that write fails nothing is released,
+ *   - the bundle file loses its flag; a payload blob loses it only if no other bundle still
+ *     references it,
+ *   - the outcome (WORM_RELEASE_COMPLETED / WORM_RELEASE_FAILED) is audited, and released
+ *     bundles are listed in <store>/worm/released.json so reopening the store does not re-lock
+ *     them.
+ *
+ * Object-lock on object storage is not implemented: the evidence store is filesystem-only.
+ */
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use audit::AuditLogger;
+
+use crate::errors::ReportingError;
+
+pub const AUDIT_COMPONENT: &str = "reporting.evidence_store";
+pub const RELEASE_AUTHORIZED: &str = "WORM_RELEASE_AUTHORIZED";
+pub const RELEASE_COMPLETED: &str = "WORM_RELEASE_COMPLETED";
+pub const RELEASE_FAILED: &str = "WORM_RELEASE_FAILED";
+
+const RELEASED_FILE: &str = "released.json";
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use std::fs::File;
+    use std::io;
+    use std::os::unix::io::AsRawFd;
+    use std::path::Path;
+
+    // _IOR('f', 1, long) / _IOW('f', 2, long); the kernel reads and writes an int
+    #[cfg(target_pointer_width = "64")]
+    const FS_IOC_GETFLAGS: libc::c_ulong = 0x8008_6601;
+    #[cfg(target_pointer_width = "64")]
+    const FS_IOC_SETFLAGS: libc::c_ulong = 0x4008_6602;
+    #[cfg(target_pointer_width = "32")]
+    const FS_IOC_GETFLAGS: libc::c_ulong = 0x8004_6601;
+    #[cfg(target_pointer_width = "32")]
+    const FS_IOC_SETFLAGS: libc::c_ulong = 0x4004_6602;
+    const FS_IMMUTABLE_FL: libc::c_int = 0x0000_0010;
+
+    fn get_flags(file: &File) -> io::Result<libc::c_int> {
+        let mut flags: libc::c_int = 0;
+        // SAFETY: FS_IOC_GETFLAGS writes one c_int through the pointer, which outlives the call
+        let rc = unsafe { libc::ioctl(file.as_raw_fd(), FS_IOC_GETFLAGS as _, &mut flags) };
+        if rc < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(flags)
+    }
+
+    fn set_flags(file: &File, flags: libc::c_int) -> io::Result<()> {
+        // SAFETY: FS_IOC_SETFLAGS reads one c_int through the pointer, which outlives the call
+        let rc = unsafe { libc::ioctl(file.as_raw_fd(), FS_IOC_SETFLAGS as _, &flags) };
+        if rc < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    pub fn is_immutable(path: &Path) -> io::Result<bool> {
+        Ok(get_flags(&File::open(path)?)? & FS_IMMUTABLE_FL != 0)
+    }
+
+    pub fn set_immutable(path: &Path, immutable: bool) -> io::Result<()> {
+        // O_RDONLY is enough for the flags ioctls and works on files that are already immutable
+        let file = File::open(path)?;
+        let flags = get_flags(&file)?;
+        let wanted = if immutable { flags | FS_IMMUTABLE_FL } else { flags & !FS_IMMUTABLE_FL };
+        if wanted != flags {
+            set_flags(&file, wanted)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod sys {
+    use std::io;
+    use std::path::Path;
+
+    fn unsupported() -> io::Error {
+        io::Error::new(io::ErrorKind::Unsupported, "filesystem immutability flags require Linux")
+    }
+
+    pub fn is_immutable(_path: &Path) -> io::Result<bool> {
+        Err(unsupported())
+    }
+
+    pub fn set_immutable(_path: &Path, _immutable: bool) -> io::Result<()> {
+        Err(unsupported())
+    }
+}
+
+fn worm_error(action: &str, path: &Path, e: std::io::Error) -> ReportingError {
+    let hint = match e.raw_os_error() {
+        Some(code) if code == libc::EPERM => " (requires CAP_LINUX_IMMUTABLE)",
+        Some(code) if code == libc::ENOTTY || code == libc::EOPNOTSUPP => " (filesystem does not support immutable files)",
+        _ => "",
+    };
+    ReportingError::WormViolation(format!("Failed to {} {}: {}{}", action, path.display(), e, hint))
+}
+
+/// Make a finalized file immutable.
+pub fn lock(path: &Path) -> Result<(), ReportingError> {
+    sys::set_immutable(path, true).map_err(|e| worm_error("set immutable flag on", path, e))
+}
+
+/// Clear the immutable flag (privileged; only via the audited release workflow).
+fn unlock(path: &Path) -> Result<(), ReportingError> {
+    sys::set_immutable(path, false).map_err(|e| worm_error("clear immutable flag on", path, e))
+}
+
+pub fn is_locked(path: &Path) -> Result<bool, ReportingError> {
+    sys::is_immutable(path).map_err(|e| worm_error("read flags of", path, e))
+}
+
+/// Check that immutable files can be created in `dir` (filesystem support and capability).
+pub fn probe(dir: &Path) -> Result<(), ReportingError> {
+    fs::create_dir_all(dir)?;
+    let probe = dir.join(format!(".worm_probe_{}", uuid::Uuid::new_v4()));
+    fs::write(&probe, b"probe")?;
+    let result = lock(&probe).and_then(|_| unlock(&probe));
+    let _ = fs::remove_file(&probe);
+    result
+}
+
+/// Explicit request to release one sealed bundle from WORM protection.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReleaseRequest {
+    pub bundle_id: String,
+    /// Must equal the sealed bundle's hash
+    pub bundle_hash: String,
+    pub actor: String,
+    pub approver: String,
+    pub reason: String,
+}
+
+impl ReleaseRequest {
+    pub fn validate(&self) -> Result<(), ReportingError> {
+        let reject = |msg: &str| Err(ReportingError::WormViolation(format!("Release of {} rejected: {}", self.bundle_id, msg)));
+        if self.actor.trim().is_empty() || self.approver.trim().is_empty() {
+            return reject("actor and approver are required");
+        }
+        if self.actor.trim().eq_ignore_ascii_case(self.approver.trim()) {
+            return reject("approver must be a different person than the actor");
+        }
+        if self.reason.trim().is_empty() {
+            return reject("a reason is required");
+        }
+        Ok(())
+    }
+}
+
+/// Released bundle, as recorded in worm/released.json.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReleaseRecord {
+    pub bundle_hash: String,
+    pub released_at: DateTime<Utc>,
+    pub actor: String,
+    pub approver: String,
+    pub audit_record_id: String,
+}
+
+/// worm/released.json - bundles deliberately released, which the store must not re-lock.
+pub struct ReleaseLedger {
+    path: PathBuf,
+    records: BTreeMap<String, ReleaseRecord>,
+}
+
+impl ReleaseLedger {
+    pub fn open(store_path: &Path) -> Result<Self, ReportingError> {
+        let path = store_path.join("worm").join(RELEASED_FILE);
+        let records = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(Self { path, records })
+    }
+
+    pub fn is_released(&self, bundle_id: &str) -> bool {
+        self.records.contains_key(bundle_id)
+    }
+
+    pub fn get(&self, bundle_id: &str) -> Option<&ReleaseRecord> {
+        self.records.get(bundle_id)
+    }
+
+    pub fn record(&mut self, bundle_id: &str, record: ReleaseRecord) -> Result<(), ReportingError> {
+        self.records.insert(bundle_id.to_string(), record);
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(&self.records)?)?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+/// Audit, unlock and record one release. `files` are the bundle file and the payload blobs no
+/// other bundle references.
+pub(crate) fn release(
+    request: &ReleaseRequest,
+    files: &[PathBuf],
+    ledger: &mut ReleaseLedger,
+    audit: &mut AuditLogger,
+) -> Result<ReleaseRecord, ReportingError> {
+    let host = hostname();
+    let file_list: Vec<String> = files.iter().map(|p| p.display().to_string()).collect();
+    let audit_err = |e: audit::AuditError| {
+        ReportingError::WormViolation(format!("Release of {} aborted: audit log write failed: {}", request.bundle_id, e))
+    };
+
+    // Audit first: no audit record, no release
+    let audit_record_id = audit
+        .log(
+            AUDIT_COMPONENT,
+            RELEASE_AUTHORIZED,
+            &request.actor,
+            &host,
+            serde_json::json!({
+                "bundle_id": request.bundle_id,
+                "bundle_hash": request.bundle_hash,
+                "approver": request.approver,
+                "reason": request.reason,
+                "files": file_list,
+            }),
+        )
+        .map_err(audit_err)?;
+
+    let outcome = files.iter().try_for_each(|f| unlock(f));
+    if let Err(e) = outcome {
+        audit
+            .log(
+                AUDIT_COMPONENT,
+                RELEASE_FAILED,
+                &request.actor,
+                &host,
+                serde_json::json!({
+                    "bundle_id": request.bundle_id,
+                    "authorization": audit_record_id,
+                    "error": e.to_string(),
+                }),
+            )
+            .map_err(audit_err)?;
+        return Err(e);
+    }
+
+    let record = ReleaseRecord {
+        bundle_hash: request.bundle_hash.clone(),
+        released_at: Utc::now(),
+        actor: request.actor.clone(),
+        approver: request.approver.clone(),
+        audit_record_id: audit_record_id.clone(),
+    };
+    ledger.record(&request.bundle_id, record.clone())?;
+    audit
+        .log(
+            AUDIT_COMPONENT,
+            RELEASE_COMPLETED,
+            &request.actor,
+            &host,
+            serde_json::json!({
+                "bundle_id": request.bundle_id,
+                "authorization": audit_record_id,
+                "files": file_list,
+            }),
+        )
+        .map_err(audit_err)?;
+    Ok(record)
+}
+
+pub(crate) fn hostname() -> String {
+    fs::read_to_string("/proc/sys/kernel/hostname")
+        .map(|h| h.trim().to_string())
+        .ok()
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
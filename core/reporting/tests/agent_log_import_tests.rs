@@ -0,0 +1,157 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_reporting/tests/agent_log_import_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Sealed agent log import tests - validates sealing of spooled log segments, rejection of altered segments, and chain verification reporting gaps, forks and signer key changes
+
+#![cfg(feature = "future-reporting")]
+
+use base64::{engine::general_purpose, Engine as _};
+use reporting::agent_log_import::{self, LogSegment, AGENT_LOG_SOURCE_TYPE, REJECTED_DIR};
+use reporting::*;
+use ring::rand::SystemRandom;
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+use uuid::Uuid;
+
+const AGENT: &str = "5b0c2f4e-9d1a-4c3b-8e7f-6a5d4c3b2a10";
+const CHAIN: &str = "c4a1e7d2-3b5f-4a6c-9e8d-7f1a2b3c4d5e";
+const BOOT: &str = "0f9e8d7c-6b5a-4c3d-2e1f-0a9b8c7d6e5f";
+
+fn key_pair() -> Ed25519KeyPair {
+    let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+    Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap()
+}
+
+/// Seal `count` segments of three lines each, linked from the chain genesis.
+fn seal_chain(key: &Ed25519KeyPair, count: u64) -> Vec<LogSegment> {
+    let mut prev = agent_log_import::genesis_hash(BOOT, CHAIN);
+    (0..count)
+        .map(|index| {
+            let lines: Vec<String> = (0..3).map(|n| format!("2026-03-14T08:00:0{}Z INFO line {}.{}", n, index, n)).collect();
+            let head = lines.iter().fold(prev.clone(), |p, l| agent_log_import::line_hash(&p, l));
+            let segment = sign(
+                key,
+                LogSegment {
+                    chain_id: CHAIN.to_string(),
+                    boot_id: BOOT.to_string(),
+                    segment_index: index,
+                    first_line: index * 3,
+                    prev_hash: prev.clone(),
+                    head_hash: head.clone(),
+                    sealed_at: format!("2026-03-14T08:0{}:00.000000Z", index),
+                    segments_dropped_before: 0,
+                    lines,
+                    signer_key: String::new(),
+                    signature: String::new(),
+                },
+            );
+            prev = head;
+            segment
+        })
+        .collect()
+}
+
+fn sign(key: &Ed25519KeyPair, mut segment: LogSegment) -> LogSegment {
+    segment.signer_key = hex::encode(key.public_key().as_ref());
+    segment.signature = general_purpose::STANDARD.encode(key.sign(&segment.digest()).as_ref());
+    segment
+}
+
+fn spool(dir: &Path, segment: &LogSegment) -> Uuid {
+    let segment_id = Uuid::new_v4();
+    let entry = serde_json::json!({
+        "segment_id": segment_id,
+        "agent_id": AGENT,
+        "agent_component_id": "linux-agent-01",
+        "received_at": "2026-03-14T08:10:00Z",
+        "continuity": if segment.segment_index == 0 { "genesis" } else { "linked" },
+        "segment": segment,
+    });
+    fs::write(dir.join(format!("{}.json", segment_id)), entry.to_string()).unwrap();
+    segment_id
+}
+
+fn import(temp_dir: &TempDir, segments: &[LogSegment]) -> (EvidenceStore, agent_log_import::ImportOutcome) {
+    let spool_dir = temp_dir.path().join("spool");
+    fs::create_dir_all(&spool_dir).unwrap();
+    for segment in segments {
+        spool(&spool_dir, segment);
+    }
+    let store = EvidenceStore::new(temp_dir.path().join("store"), None).unwrap();
+    let collector = EvidenceCollector::new("1.0.0", "1.0.0");
+    let outcome = agent_log_import::import_segments(&store, &collector, "1.0.0", "1.0.0", &spool_dir).unwrap();
+    (store, outcome)
+}
+
+#[test]
+fn test_import_seals_segments_and_rejects_altered_lines() {
+    let temp_dir = TempDir::new().unwrap();
+    let key = key_pair();
+    let mut segments = seal_chain(&key, 3);
+    let mut altered = segments[2].clone();
+    altered.lines[1] = "2026-03-14T08:00:01Z INFO nothing happened".to_string();
+    segments[2] = altered;
+
+    let (store, outcome) = import(&temp_dir, &segments);
+    assert_eq!(outcome.imported.len(), 2);
+    assert_eq!(outcome.rejected.len(), 1);
+    assert!(outcome.rejected[0].reason.contains("head_hash"), "{}", outcome.rejected[0].reason);
+
+    let spool_dir = temp_dir.path().join("spool");
+    let left: Vec<_> = fs::read_dir(&spool_dir).unwrap().filter_map(|e| e.ok()).filter(|e| e.path().is_file()).collect();
+    assert!(left.is_empty(), "imported segments leave the spool");
+    assert_eq!(fs::read_dir(spool_dir.join(REJECTED_DIR)).unwrap().count(), 1);
+
+    let bundle = store.get_bundle(&outcome.imported[0].bundle_id).unwrap();
+    let evidence = &bundle.evidence_items[0];
+    assert_eq!(evidence.source_type, AGENT_LOG_SOURCE_TYPE);
+    assert_eq!(evidence.metadata["agent_id"], AGENT);
+    assert_eq!(evidence.metadata["chain_id"], CHAIN);
+    assert_eq!(evidence.data["segment"]["lines"].as_array().unwrap().len(), 3);
+}
+
+#[test]
+fn test_verify_reports_intact_chain_and_gaps() {
+    let temp_dir = TempDir::new().unwrap();
+    let key = key_pair();
+    let segments = seal_chain(&key, 5);
+    // Segments 1 and 2 never arrived; segment 4 was resent and imported twice
+    let shipped = [segments[0].clone(), segments[3].clone(), segments[4].clone(), segments[4].clone()];
+    let (store, outcome) = import(&temp_dir, &shipped);
+    assert_eq!(outcome.imported.len(), 4);
+
+    let reports = agent_log_import::verify_agent_logs(&store, &AGENT.to_uppercase()).unwrap();
+    assert_eq!(reports.len(), 1);
+    let report = &reports[0];
+    assert!(report.is_intact(), "{:?}", report.problems);
+    assert_eq!(report.segments, 3);
+    assert_eq!(report.lines, 9);
+    assert_eq!(report.last_index, 4);
+    assert_eq!(report.gaps, vec![(1, 2)]);
+
+    assert!(agent_log_import::verify_agent_logs(&store, "7c2f4a56-0a3e-4c1b-9a55-1f1f4c0e2b11").is_err());
+}
+
+#[test]
+fn test_verify_flags_forks_and_key_changes() {
+    let temp_dir = TempDir::new().unwrap();
+    let key = key_pair();
+    let mut segments = seal_chain(&key, 3);
+    // A validly sealed segment 1 that does not follow segment 0
+    let mut forked = segments[1].clone();
+    forked.prev_hash = agent_log_import::line_hash(&forked.prev_hash, "inserted");
+    forked.head_hash = forked.lines.iter().fold(forked.prev_hash.clone(), |p, l| agent_log_import::line_hash(&p, l));
+    segments[1] = sign(&key, forked);
+    // Segment 2 sealed by another key
+    segments[2] = sign(&key_pair(), segments[2].clone());
+
+    let (store, outcome) = import(&temp_dir, &segments);
+    assert_eq!(outcome.imported.len(), 3, "each segment verifies on its own");
+
+    let report = &agent_log_import::verify_agent_logs(&store, AGENT).unwrap()[0];
+    assert!(!report.is_intact());
+    assert!(report.problems.iter().any(|p| p.contains("does not follow segment 0")), "{:?}", report.problems);
+    assert!(report.problems.iter().any(|p| p.contains("signer key changed")), "{:?}", report.problems);
+    assert!(report.gaps.is_empty());
+}
@@ -0,0 +1,148 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_reporting/tests/annotation_export_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Annotation export tests - validates loading of the annotation export, refusal of incomplete revision histories, selection of notes linked to report evidence and their full history in JSON, HTML and CSV exports
+
+#![cfg(feature = "future-reporting")]
+
+use reporting::*;
+use tempfile::TempDir;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+const INCIDENT: &str = "7c2f4a56-0a3e-4c1b-9a55-1f1f4c0e2b11";
+const DETECTION: &str = "0d9a1e2b-5c3f-4e8a-b7d6-2a4c6e8f0b13";
+
+fn note(id: &str, subject_type: &str, subject_id: &str, revisions: serde_json::Value) -> serde_json::Value {
+    serde_json::json!({
+        "annotation_id": id,
+        "subject_type": subject_type,
+        "subject_id": subject_id,
+        "created_by": "analyst-1",
+        "created_at": "2026-03-14T08:00:00Z",
+        "revisions": revisions,
+    })
+}
+
+fn first(body: &str) -> serde_json::Value {
+    serde_json::json!({"revision": 1, "body": body, "author": "analyst-1", "edited_at": "2026-03-14T08:00:00Z", "edit_reason": null})
+}
+
+fn write_export(path: &Path, notes: Vec<serde_json::Value>) {
+    fs::write(path, serde_json::json!({"data": notes, "next_cursor": null, "total_estimate": 0}).to_string()).unwrap();
+}
+
+#[test]
+fn test_export_requires_complete_history() {
+    let temp_dir = TempDir::new().unwrap();
+    let export = temp_dir.path().join("annotations.json");
+    let edit = serde_json::json!({
+        "revision": 2, "body": "Scope: file servers only", "author": "analyst-2",
+        "edited_at": "2026-03-14T09:00:00Z", "edit_reason": "corrected scope"
+    });
+    write_export(&export, vec![note("a-1", "incident", INCIDENT, serde_json::json!([first("Scope: all hosts"), edit.clone()]))]);
+    assert_eq!(Annotations::load(&export).unwrap().len(), 1);
+
+    let broken = [
+        // Revision 1 missing, gap, edit without reason, original with a reason, unknown subject
+        note("a-1", "incident", INCIDENT, serde_json::json!([edit.clone()])),
+        note("a-1", "incident", INCIDENT, serde_json::json!([first("x"), {"revision": 3, "body": "y", "author": "b", "edited_at": "2026-03-14T09:00:00Z", "edit_reason": "r"}])),
+        note("a-1", "incident", INCIDENT, serde_json::json!([first("x"), {"revision": 2, "body": "y", "author": "b", "edited_at": "2026-03-14T09:00:00Z"}])),
+        note("a-1", "incident", INCIDENT, serde_json::json!([{"revision": 1, "body": "x", "author": "analyst-1", "edited_at": "2026-03-14T08:00:00Z", "edit_reason": "r"}])),
+        note("a-1", "entity", INCIDENT, serde_json::json!([first("x")])),
+        note("a-1", "incident", INCIDENT, serde_json::json!([])),
+    ];
+    for entry in broken {
+        write_export(&export, vec![entry.clone()]);
+        assert!(
+            matches!(Annotations::load(&export), Err(ReportingError::InvalidConfiguration(_))),
+            "accepted {}",
+            entry
+        );
+    }
+}
+
+#[test]
+fn test_linked_notes_and_history_in_exports() {
+    let temp_dir = TempDir::new().unwrap();
+    let store = EvidenceStore::new(temp_dir.path().join("store"), None).unwrap();
+    let collector = EvidenceCollector::new("1.0.0", "1.0.0");
+    let bundle_id = store.create_bundle("1.0.0", "1.0.0").unwrap();
+    let by_incident = collector
+        .collect(
+            "linux_agent",
+            "detection",
+            serde_json::json!({"detection_id": DETECTION, "process": "vssadmin.exe"}),
+            None,
+            HashMap::from([("incident_id".to_string(), INCIDENT.to_uppercase())]),
+        )
+        .unwrap();
+    let evidence_id = by_incident.evidence_id.clone();
+    store.add_evidence(&bundle_id, by_incident).unwrap();
+    store.seal_bundle(&bundle_id).unwrap();
+    let bundles = vec![store.get_bundle(&bundle_id).unwrap()];
+
+    let export = temp_dir.path().join("annotations.json");
+    write_export(
+        &export,
+        vec![
+            note(
+                "a-incident",
+                "incident",
+                INCIDENT,
+                serde_json::json!([
+                    first("Initial access via 10.0.0.5"),
+                    {"revision": 2, "body": "Initial access via VPN account", "author": "analyst-2",
+                     "edited_at": "2026-03-14T09:00:00Z", "edit_reason": "confirmed with VPN logs"}
+                ]),
+            ),
+            note("a-detection", "detection", DETECTION, serde_json::json!([first("True positive")])),
+            note("a-entry", "timeline_entry", &evidence_id, serde_json::json!([first("First encryption event")])),
+            note("a-other", "incident", "99999999-0000-0000-0000-000000000000", serde_json::json!([first("Unrelated")])),
+        ],
+    );
+    let annotations = Annotations::load(&export).unwrap();
+
+    let report = ReportBuilder::new("1.0.0", "1.0.0", "build_hash", None)
+        .with_annotations(annotations.clone())
+        .build_report("Incident Report", "Annotated", &bundles, None)
+        .unwrap();
+    let ids: Vec<&str> = report.annotations.iter().map(|a| a.annotation_id.as_str()).collect();
+    assert_eq!(ids, ["a-detection", "a-entry", "a-incident"]);
+    let incident_note = report.annotations.iter().find(|a| a.annotation_id == "a-incident").unwrap();
+    assert_eq!(incident_note.revisions.len(), 2, "earlier revisions are kept");
+    assert_eq!(incident_note.current().author, "analyst-2");
+    let section = report.sections.iter().find(|s| s.title == "Analyst Annotations").unwrap();
+    assert_eq!(section.subsections.len(), 3);
+
+    let exporter = ReportExporter::new();
+    let html_path = temp_dir.path().join("report.html");
+    exporter.export_html(&report, &html_path).unwrap();
+    let html = fs::read_to_string(&html_path).unwrap();
+    assert!(html.contains("Initial access via VPN account") && html.contains("Initial access via 10.0.0.5"));
+    assert!(html.contains("confirmed with VPN logs"));
+
+    let csv_path = temp_dir.path().join("report.csv");
+    exporter.export_csv(&report, &csv_path).unwrap();
+    let csv = fs::read_to_string(&csv_path).unwrap();
+    assert!(csv.contains("a-incident incident 7c2f4a56-0a3e-4c1b-9a55-1f1f4c0e2b11 rev 1 by analyst-1"));
+    assert!(csv.contains("rev 2 by analyst-2 at 2026-03-14T09:00:00+00:00 (confirmed with VPN logs)"));
+
+    exporter.export_pdf(&report, temp_dir.path().join("report.pdf")).unwrap();
+
+    // Executive audience: free-text addresses redacted in every revision
+    let executive = ReportBuilder::new("1.0.0", "1.0.0", "build_hash", None)
+        .with_redaction(RedactionProfile::Executive)
+        .with_annotations(annotations)
+        .build_report("Incident Report", "Annotated", &bundles, None)
+        .unwrap();
+    let incident_note = executive.annotations.iter().find(|a| a.annotation_id == "a-incident").unwrap();
+    assert_eq!(incident_note.revisions[0].body, "Initial access via [REDACTED:ip_address]");
+
+    // Without an export nothing is added
+    let plain = ReportBuilder::new("1.0.0", "1.0.0", "build_hash", None)
+        .build_report("Incident Report", "Plain", &bundles, None)
+        .unwrap();
+    assert!(plain.annotations.is_empty());
+    assert!(plain.sections.iter().all(|s| s.title != "Analyst Annotations"));
+}
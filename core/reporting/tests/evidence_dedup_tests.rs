@@ -0,0 +1,127 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_reporting/tests/evidence_dedup_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Evidence deduplication tests - validates single storage of identical payloads, reference counting, compression above the threshold and integrity verification of stored payloads
+
+#![cfg(feature = "future-reporting")]
+
+use reporting::*;
+use tempfile::TempDir;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+fn seal(store: &EvidenceStore, data: serde_json::Value) -> String {
+    let collector = EvidenceCollector::new("1.0.0", "1.0.0");
+    let bundle_id = store.create_bundle("1.0.0", "1.0.0").unwrap();
+    let evidence = collector.collect("test_source", "test_type", data, None, HashMap::new()).unwrap();
+    store.add_evidence(&bundle_id, evidence).unwrap();
+    store.seal_bundle(&bundle_id).unwrap();
+    bundle_id
+}
+
+fn blob_files(store_path: &Path) -> Vec<std::path::PathBuf> {
+    WalkDir::new(store_path.join("blobs"))
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .collect()
+}
+
+#[test]
+fn test_identical_payloads_stored_once() {
+    let temp_dir = TempDir::new().unwrap();
+    let store_path = temp_dir.path().join("store");
+    let store = EvidenceStore::new(&store_path, None).unwrap();
+
+    let payload = serde_json::json!({"process": "vssadmin.exe", "args": "delete shadows /all"});
+    seal(&store, payload.clone());
+    seal(&store, payload);
+    seal(&store, serde_json::json!({"process": "other.exe"}));
+
+    assert_eq!(blob_files(&store_path).len(), 2);
+    let stats = store.storage_stats();
+    assert_eq!(stats.unique_blobs, 2);
+    assert_eq!(stats.references, 3);
+    assert!(stats.dedup_saved_bytes() > 0);
+}
+
+#[test]
+fn test_reload_rehydrates_and_verifies() {
+    let temp_dir = TempDir::new().unwrap();
+    let store_path = temp_dir.path().join("store");
+    let payload = serde_json::json!({"blob": "x".repeat(10_000)});
+    let bundle_id = {
+        let store = EvidenceStore::new(&store_path, None).unwrap();
+        seal(&store, payload.clone())
+    };
+
+    // Bundle file holds a reference, not the payload
+    let bundle_json = fs::read_to_string(store_path.join("bundles").join(format!("{}.json", bundle_id))).unwrap();
+    assert!(bundle_json.contains("data_blob"));
+    assert!(!bundle_json.contains(&"x".repeat(100)));
+
+    // Large payload is compressed on disk
+    let files = blob_files(&store_path);
+    assert_eq!(files.len(), 1);
+    assert!(files[0].to_string_lossy().ends_with(".zst"));
+
+    let store = EvidenceStore::new(&store_path, None).unwrap();
+    let bundle = store.get_bundle(&bundle_id).unwrap();
+    assert_eq!(bundle.evidence_items[0].data, payload);
+    assert!(store.verify_bundle_integrity(&bundle).unwrap());
+    assert!(store.storage_stats().compression_saved_bytes() > 0);
+}
+
+#[test]
+fn test_tampered_blob_fails_closed() {
+    let temp_dir = TempDir::new().unwrap();
+    let store_path = temp_dir.path().join("store");
+    {
+        let store = EvidenceStore::new(&store_path, None).unwrap();
+        seal(&store, serde_json::json!({"test": "data"}));
+    }
+
+    let blob = &blob_files(&store_path)[0];
+    fs::write(blob, br#"{"test":"tampered"}"#).unwrap();
+
+    let result = EvidenceStore::new(&store_path, None);
+    assert!(matches!(result, Err(ReportingError::HashMismatch { .. })));
+}
+
+#[test]
+fn test_purge_releases_shared_payload_last() {
+    let temp_dir = TempDir::new().unwrap();
+    let store_path = temp_dir.path().join("store");
+    let store = EvidenceStore::new(&store_path, None).unwrap();
+
+    let payload = serde_json::json!({"shared": true});
+    let first = seal(&store, payload.clone());
+    let second = seal(&store, payload);
+
+    store.purge_bundle(&first).unwrap();
+    assert_eq!(blob_files(&store_path).len(), 1);
+    assert_eq!(store.storage_stats().references, 1);
+
+    store.purge_bundle(&second).unwrap();
+    assert!(blob_files(&store_path).is_empty());
+    assert_eq!(store.storage_stats(), StorageStats::default());
+}
+
+#[test]
+fn test_small_payloads_stay_uncompressed() {
+    let temp_dir = TempDir::new().unwrap();
+    let store_path = temp_dir.path().join("store");
+    let store = EvidenceStore::open(
+        &store_path,
+        None,
+        EvidenceStoreOptions { compression_threshold: 1 << 20, ..EvidenceStoreOptions::default() },
+    ).unwrap();
+    seal(&store, serde_json::json!({"blob": "x".repeat(10_000)}));
+
+    let files = blob_files(&store_path);
+    assert_eq!(files.len(), 1);
+    assert!(!files[0].to_string_lossy().ends_with(".zst"));
+    assert_eq!(store.storage_stats().compression_saved_bytes(), 0);
+}
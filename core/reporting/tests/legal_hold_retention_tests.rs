@@ -0,0 +1,100 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_reporting/tests/legal_hold_retention_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Legal hold retention tests - validates hold export parsing, exemption of bundles linked to held incidents/entities and skipped counts in the purge ledger and destruction certificate
+
+#![cfg(feature = "future-retention")]
+
+use reporting::*;
+use tempfile::TempDir;
+use std::collections::HashMap;
+use std::fs;
+
+const HELD_INCIDENT: &str = "7c2f4a56-0a3e-4c1b-9a55-1f1f4c0e2b11";
+const HELD_ENTITY: &str = "0d9a1e2b-5c3f-4e8a-b7d6-2a4c6e8f0b13";
+
+fn seal(store: &EvidenceStore, data: serde_json::Value, metadata: HashMap<String, String>) -> String {
+    let collector = EvidenceCollector::new("1.0.0", "1.0.0");
+    let bundle_id = store.create_bundle("1.0.0", "1.0.0").unwrap();
+    let evidence = collector.collect("test_source", "test_type", data, None, metadata).unwrap();
+    store.add_evidence(&bundle_id, evidence).unwrap();
+    store.seal_bundle(&bundle_id).unwrap();
+    bundle_id
+}
+
+fn expire_everything() -> RetentionPolicy {
+    RetentionPolicy { forensic_retention_days: 0, ..RetentionPolicy::default() }
+}
+
+#[test]
+fn test_hold_export_parsing() {
+    let temp_dir = TempDir::new().unwrap();
+    let export = temp_dir.path().join("holds.json");
+    fs::write(&export, serde_json::json!({
+        "data": [
+            {"subject_type": "incident", "subject_id": HELD_INCIDENT, "active": true},
+            {"subject_type": "entity", "subject_id": "11111111-2222-3333-4444-555555555555", "active": false},
+        ],
+        "next_cursor": null,
+        "total_estimate": 2
+    }).to_string()).unwrap();
+    let holds = LegalHolds::load(&export).unwrap();
+    assert!(!holds.is_empty());
+
+    fs::write(&export, r#"[{"subject_type": "case", "subject_id": "x"}]"#).unwrap();
+    assert!(matches!(LegalHolds::load(&export), Err(ReportingError::InvalidConfiguration(_))));
+}
+
+#[test]
+fn test_held_bundles_survive_purge_and_are_counted() {
+    let temp_dir = TempDir::new().unwrap();
+    let store = EvidenceStore::new(temp_dir.path().join("store"), None).unwrap();
+
+    let by_incident = seal(
+        &store,
+        serde_json::json!({"process": "vssadmin.exe"}),
+        HashMap::from([("incident_id".to_string(), HELD_INCIDENT.to_uppercase())]),
+    );
+    let by_entity = seal(&store, serde_json::json!({"target_entity_id": HELD_ENTITY}), HashMap::new());
+    let unheld = seal(
+        &store,
+        serde_json::json!({"process": "other.exe"}),
+        HashMap::from([("incident_id".to_string(), "99999999-0000-0000-0000-000000000000".to_string())]),
+    );
+
+    let mut holds = LegalHolds::new();
+    holds.hold_incident(HELD_INCIDENT);
+    holds.hold_entity(HELD_ENTITY);
+
+    let ledger_path = temp_dir.path().join("retention").join("purge_ledger.jsonl");
+    let manager = RetentionManager::new(store, expire_everything(), &ledger_path)
+        .unwrap()
+        .with_legal_holds(holds);
+
+    let purged = manager.enforce_retention(false).unwrap();
+    assert_eq!(purged, vec![unheld]);
+
+    let ledger = fs::read_to_string(&ledger_path).unwrap();
+    let event: PurgeEvent = serde_json::from_str(ledger.lines().next().unwrap()).unwrap();
+    assert_eq!(event.legal_hold_skipped, 2);
+    assert!(event.legal_hold_bundle_ids.contains(&by_incident));
+    assert!(event.legal_hold_bundle_ids.contains(&by_entity));
+
+    let certificate: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(event.destruction_certificate_path.unwrap()).unwrap()).unwrap();
+    assert_eq!(certificate["total_bundles"], 1);
+    assert_eq!(certificate["legal_hold_skipped"], 2);
+}
+
+#[test]
+fn test_without_holds_everything_expired_is_purged() {
+    let temp_dir = TempDir::new().unwrap();
+    let store = EvidenceStore::new(temp_dir.path().join("store"), None).unwrap();
+    seal(
+        &store,
+        serde_json::json!({"process": "vssadmin.exe"}),
+        HashMap::from([("incident_id".to_string(), HELD_INCIDENT.to_string())]),
+    );
+
+    let manager = RetentionManager::new(store, expire_everything(), temp_dir.path().join("ledger.jsonl")).unwrap();
+    assert_eq!(manager.enforce_retention(true).unwrap().len(), 1);
+}
@@ -0,0 +1,132 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_reporting/tests/memory_import_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Memory image import tests - validates sealing of spooled images as verified chunks with their chain of custody, rejection of images that do not match their manifest, verified export, and chunk references across reopen and purge
+
+#![cfg(feature = "future-reporting")]
+
+use reporting::memory_import::{self, MEMORY_SOURCE_TYPE, REJECTED_DIR};
+use reporting::*;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+const INCIDENT: &str = "7c2f4a56-0a3e-4c1b-9a55-1f1f4c0e2b11";
+
+fn image_chunks() -> Vec<Vec<u8>> {
+    vec![vec![0x11; 4096], vec![0x22; 4096], vec![0x33; 1000]]
+}
+
+fn spool(dir: &Path, acquisition_id: &str, chunks: &[Vec<u8>]) {
+    let chunk_dir = dir.join(acquisition_id);
+    fs::create_dir_all(&chunk_dir).unwrap();
+    let mut entries = Vec::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        fs::write(chunk_dir.join(format!("{:08}.chunk", i)), chunk).unwrap();
+        entries.push(serde_json::json!({"index": i, "sha256": hex::encode(Sha256::digest(chunk)), "bytes": chunk.len()}));
+    }
+    let image = chunks.concat();
+    let manifest = serde_json::json!({
+        "acquisition_id": acquisition_id,
+        "agent_id": "5b0c2f4e-9d1a-4c3b-8e7f-6a5d4c3b2a10",
+        "agent_component_id": "linux-agent-01",
+        "incident_id": INCIDENT,
+        "scope": "process",
+        "pid": 4242,
+        "reason": "INC-42 suspected injected loader",
+        "requested_by": "analyst.one",
+        "approved_by": "lead.two",
+        "requested_at": "2026-03-14T08:00:00Z",
+        "claimed_at": "2026-03-14T08:00:20Z",
+        "completed_at": "2026-03-14T08:03:30Z",
+        "tool": "/usr/bin/gcore",
+        "tool_version": null,
+        "image_sha256": hex::encode(Sha256::digest(&image)),
+        "image_bytes": image.len(),
+        "chunk_dir": acquisition_id,
+        "chunks": entries,
+        "custody": [
+            {"at": "2026-03-14T08:00:00Z", "action": "requested", "actor": "analyst.one", "detail": "operator request"},
+            {"at": "2026-03-14T08:00:00Z", "action": "approved", "actor": "lead.two", "detail": "second-person approval"},
+        ],
+    });
+    fs::write(dir.join(format!("{}.json", acquisition_id)), manifest.to_string()).unwrap();
+}
+
+#[test]
+fn test_import_seals_chunks_and_rejects_mismatches() {
+    let temp_dir = TempDir::new().unwrap();
+    let spool_dir = temp_dir.path().join("spool");
+    fs::create_dir_all(&spool_dir).unwrap();
+    let chunks = image_chunks();
+    let good = "3f1e2d4c-5b6a-4978-8a9b-0c1d2e3f4a5b";
+    let tampered = "4a2b3c4d-5e6f-4a1b-9c2d-3e4f5a6b7c8d";
+    spool(&spool_dir, good, &chunks);
+    spool(&spool_dir, tampered, &chunks);
+    fs::write(spool_dir.join(tampered).join("00000001.chunk"), vec![0x23; 4096]).unwrap();
+
+    let store_path = temp_dir.path().join("store");
+    let store = EvidenceStore::new(&store_path, None).unwrap();
+    let collector = EvidenceCollector::new("1.0.0", "1.0.0");
+    let outcome = memory_import::import_images(&store, &collector, "1.0.0", "1.0.0", &spool_dir).unwrap();
+    assert_eq!(outcome.imported.len(), 1);
+    assert_eq!(outcome.imported[0].acquisition_id.to_string(), good);
+    assert_eq!(outcome.rejected.len(), 1);
+    assert!(outcome.rejected[0].reason.contains("chunk 1"), "{}", outcome.rejected[0].reason);
+
+    // Imported images leave the spool; rejected ones are kept aside, never sealed
+    assert!(!spool_dir.join(format!("{}.json", good)).exists());
+    assert!(!spool_dir.join(good).exists());
+    assert!(spool_dir.join(REJECTED_DIR).join(tampered).join("00000001.chunk").exists());
+
+    let bundle = store.get_bundle(&outcome.imported[0].bundle_id).unwrap();
+    let evidence = &bundle.evidence_items[0];
+    assert_eq!(evidence.source_type, MEMORY_SOURCE_TYPE);
+    assert_eq!(evidence.metadata["incident_id"], INCIDENT);
+    assert_eq!(evidence.chunks.len(), 3);
+    // The image stays out of the evidence payload; the custody chain ends with the seal
+    assert!(evidence.data.get("image").is_none());
+    let custody = evidence.data["custody"].as_array().unwrap();
+    assert_eq!(custody.last().unwrap()["action"], "sealed");
+
+    let out = temp_dir.path().join("image.bin");
+    let found = memory_import::find_image(&store, &good.to_uppercase()).unwrap();
+    assert_eq!(memory_import::export_image(&store, &found, &out).unwrap(), chunks.concat().len() as u64);
+    assert_eq!(fs::read(&out).unwrap(), chunks.concat());
+    assert!(memory_import::find_image(&store, tampered).is_err());
+}
+
+#[test]
+fn test_chunks_survive_reopen_and_are_released_on_purge() {
+    let temp_dir = TempDir::new().unwrap();
+    let spool_dir = temp_dir.path().join("spool");
+    fs::create_dir_all(&spool_dir).unwrap();
+    let acquisition = "3f1e2d4c-5b6a-4978-8a9b-0c1d2e3f4a5b";
+    spool(&spool_dir, acquisition, &image_chunks());
+
+    let store_path = temp_dir.path().join("store");
+    let bundle_id = {
+        let store = EvidenceStore::new(&store_path, None).unwrap();
+        let collector = EvidenceCollector::new("1.0.0", "1.0.0");
+        let outcome = memory_import::import_images(&store, &collector, "1.0.0", "1.0.0", &spool_dir).unwrap();
+        outcome.imported[0].bundle_id.clone()
+    };
+
+    let store = EvidenceStore::new(&store_path, None).unwrap();
+    let evidence = memory_import::find_image(&store, acquisition).unwrap();
+    let first_chunk = evidence.chunks[0].clone();
+    assert_eq!(store.read_chunk(&first_chunk).unwrap(), image_chunks()[0]);
+
+    // A chunk changed in the blob store fails the export and leaves no output behind
+    let out = temp_dir.path().join("image.bin");
+    // The 1000-byte tail is below the compression threshold: stored as is under its digest
+    let tail = &evidence.chunks[2];
+    let blob = store_path.join("blobs").join(&tail[..2]).join(tail);
+    fs::write(&blob, vec![0x34; 1000]).unwrap();
+    assert!(memory_import::export_image(&store, &evidence, &out).is_err());
+    assert!(!out.exists());
+    fs::write(&blob, vec![0x33; 1000]).unwrap();
+
+    store.purge_bundle(&bundle_id).unwrap();
+    assert!(store.read_chunk(&first_chunk).is_err(), "chunk released with its bundle");
+}
@@ -0,0 +1,144 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_reporting/tests/notification_template_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Notification template tests - validates tenant and locale override lookup, load-time rejection of broken or unknown templates and fallback to the built-in templates when an override fails to render
+
+#![cfg(feature = "future-reporting")]
+
+use reporting::*;
+use reporting::distribution::{email_body, email_subject};
+use reporting::download_token::DownloadLink;
+use reporting::notification_templates::{AlertNotification, NotificationTemplates, TemplateConfig, TemplateName};
+use reporting::scheduler::ReportArtifact;
+use chrono::{TimeZone, Utc};
+use tempfile::TempDir;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn report() -> GeneratedReport {
+    let window_end = Utc.with_ymd_and_hms(2026, 3, 14, 6, 0, 0).unwrap();
+    GeneratedReport {
+        schedule: "daily-exec".to_string(),
+        kind: ReportKind::ExecutiveSummary,
+        title: "Executive Summary".to_string(),
+        report_id: "report-1".to_string(),
+        generated_at: window_end,
+        window_start: Utc.with_ymd_and_hms(2026, 3, 13, 6, 0, 0).unwrap(),
+        window_end,
+        evidence_items: 3,
+        redaction_profile: RedactionProfile::Executive,
+        residency: "eu".to_string(),
+        artifacts: vec![ReportArtifact { file_name: "report-1_report.pdf".to_string(), sha256: "a".repeat(64), bytes: 10 }],
+        evidence_bundle_id: "bundle-1".to_string(),
+        evidence_bundle_hash: "b".repeat(64),
+        downloads: Vec::new(),
+        directory: PathBuf::new(),
+    }
+}
+
+fn alert() -> AlertNotification {
+    AlertNotification {
+        alert_id: "alert-1".to_string(),
+        severity: "high".to_string(),
+        title: "Ransom note dropped".to_string(),
+        summary: "README_DECRYPT.txt written to 40 directories".to_string(),
+        detected_at: Utc.with_ymd_and_hms(2026, 3, 14, 5, 30, 0).unwrap(),
+        host: Some("fs-01".to_string()),
+        agent_id: None,
+        incident_id: None,
+        mitre_techniques: vec!["T1486".to_string(), "T1490".to_string()],
+        link: None,
+    }
+}
+
+fn write_template(dir: &Path, scope: &str, locale: &str, name: &str, source: &str) {
+    let locale_dir = dir.join(scope).join(locale);
+    fs::create_dir_all(&locale_dir).unwrap();
+    fs::write(locale_dir.join(format!("{}.tera", name)), source).unwrap();
+}
+
+fn load(dir: &Path) -> Result<NotificationTemplates, ReportingError> {
+    NotificationTemplates::load(&TemplateConfig { dir: dir.to_path_buf(), default_locale: "en".to_string() })
+}
+
+#[test]
+fn test_builtin_templates_render_report_and_alert() {
+    let report = report();
+    assert_eq!(email_subject(&report), "[RansomEye] Executive Summary - 2026-03-14");
+    let body = email_body(&report);
+    assert!(body.starts_with("RansomEye scheduled report: Executive Summary\nSchedule: daily-exec\n"));
+    assert!(body.contains(&format!("\n{}  report-1_report.pdf\n\n©", "a".repeat(64))));
+    assert!(!body.contains("Download links"));
+
+    let mut with_links = report.clone();
+    with_links.downloads = vec![DownloadLink {
+        file_name: "report-1_report.pdf".to_string(),
+        url: "https://reports.example.com/reports/download/t".to_string(),
+        expires_at: with_links.window_end,
+        max_uses: 2,
+    }];
+    assert!(email_body(&with_links).contains(
+        "Download links (artifacts are not attached):\nreport-1_report.pdf\n  https://reports.example.com/reports/download/t (expires 2026-03-14T06:00:00+00:00, 2 download(s))\n\n©"
+    ));
+
+    let templates = NotificationTemplates::builtin();
+    assert_eq!(templates.alert_subject(&alert(), None, None), "[RansomEye] HIGH alert: Ransom note dropped on fs-01");
+    let body = templates.alert_body(&alert(), None, None);
+    assert!(body.contains("Host: fs-01\nMITRE ATT&CK: T1486, T1490\n"));
+    assert!(!body.contains("Agent:") && !body.contains("Details:"));
+}
+
+#[test]
+fn test_tenant_and_locale_overrides() {
+    let temp_dir = TempDir::new().unwrap();
+    write_template(temp_dir.path(), "default", "de", "report_subject", "[RansomEye] {{ title }} vom {{ window_end_date }}\n");
+    write_template(temp_dir.path(), "acme", "de-AT", "report_subject", "[ACME] {{ title }}");
+    write_template(temp_dir.path(), "acme", "en", "alert_subject", "[ACME SOC] {{ severity }}: {{ title }}");
+    let templates = load(temp_dir.path()).unwrap();
+    assert_eq!(templates.override_count(), 3);
+
+    let report = report();
+    assert_eq!(templates.report_subject(&report, Some("acme"), Some("de-at")), "[ACME] Executive Summary");
+    // Region falls back to the language, tenant to the default scope
+    assert_eq!(templates.report_subject(&report, Some("globex"), Some("de-CH")), "[RansomEye] Executive Summary vom 2026-03-14");
+    assert_eq!(templates.resolve(TemplateName::ReportSubject, Some("acme"), Some("de")), "default/de/report_subject");
+    // No override for the locale: built-in template
+    assert_eq!(templates.report_subject(&report, Some("acme"), Some("fr")), email_subject(&report));
+    assert_eq!(templates.report_body(&report, Some("acme"), Some("de")), email_body(&report));
+    // Default locale applies when the report sets none
+    assert_eq!(templates.alert_subject(&alert(), Some("acme"), None), "[ACME SOC] high: Ransom note dropped");
+}
+
+#[test]
+fn test_broken_overrides_are_rejected_at_load() {
+    let cases = [
+        ("default", "en", "report_body", "{% for a in artifacts %}{{ a.sha256 }}"),
+        ("default", "en", "report_body", "{{ tenant_name }}"),
+        ("default", "en", "alert_body", "{{ title | no_such_filter }}"),
+        ("default", "en", "report_digest", "{{ title }}"),
+        ("bad tenant", "en", "report_subject", "{{ title }}"),
+    ];
+    for (scope, locale, name, source) in cases {
+        let temp_dir = TempDir::new().unwrap();
+        write_template(temp_dir.path(), scope, locale, name, source);
+        let err = load(temp_dir.path()).err().unwrap_or_else(|| panic!("{}/{}/{} should be rejected", scope, locale, name));
+        assert!(matches!(err, ReportingError::InvalidConfiguration(_)), "{}", err);
+    }
+
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("report_subject.tera"), "{{ title }}").unwrap();
+    assert!(load(temp_dir.path()).is_err(), "templates must live under <scope>/<locale>/");
+}
+
+#[test]
+fn test_override_render_failure_falls_back_to_builtin() {
+    let temp_dir = TempDir::new().unwrap();
+    // Valid against the sample report, fails on a report without artifacts
+    write_template(temp_dir.path(), "acme", "en", "report_body", "First artifact: {{ artifacts.0.file_name }}\n");
+    let templates = load(temp_dir.path()).unwrap();
+
+    let report = report();
+    assert_eq!(templates.report_body(&report, Some("acme"), None), "First artifact: report-1_report.pdf\n");
+    let mut empty = report;
+    empty.artifacts.clear();
+    assert_eq!(templates.report_body(&empty, Some("acme"), None), email_body(&empty));
+}
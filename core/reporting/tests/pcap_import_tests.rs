@@ -0,0 +1,114 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_reporting/tests/pcap_import_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Packet capture import tests - validates sealing of spooled pcaps with their detection and incident links, rejection of captures that do not match their manifest, verified read-back and omission of the pcap from report evidence details
+
+#![cfg(feature = "future-reporting")]
+
+use reporting::pcap_import::{self, PCAP_DATA_KEY, PCAP_SOURCE_TYPE, REJECTED_DIR};
+use reporting::*;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+const INCIDENT: &str = "7c2f4a56-0a3e-4c1b-9a55-1f1f4c0e2b11";
+const DETECTION: &str = "0d9a1e2b-5c3f-4e8a-b7d6-2a4c6e8f0b13";
+
+fn pcap() -> Vec<u8> {
+    let mut data = vec![0xd4, 0xc3, 0xb2, 0xa1, 2, 0, 4, 0];
+    data.resize(24 + 96, 0x5a);
+    data
+}
+
+fn spool(dir: &Path, capture_id: &str, pcap: &[u8], sha256: &str) {
+    let manifest = serde_json::json!({
+        "capture_id": capture_id,
+        "detection_id": DETECTION,
+        "incident_id": INCIDENT,
+        "host_ip": "10.1.2.3",
+        "bpf_filter": "host 10.1.2.3",
+        "probe_agent_id": "5b0c2f4e-9d1a-4c3b-8e7f-6a5d4c3b2a10",
+        "probe_component_id": "dpi-probe-01",
+        "requested_at": "2026-03-14T08:00:00Z",
+        "claimed_at": "2026-03-14T08:00:20Z",
+        "uploaded_at": "2026-03-14T08:05:30Z",
+        "pcap_file": format!("{}.pcap", capture_id),
+        "pcap_sha256": sha256,
+        "pcap_bytes": pcap.len(),
+    });
+    fs::write(dir.join(format!("{}.pcap", capture_id)), pcap).unwrap();
+    fs::write(dir.join(format!("{}.json", capture_id)), manifest.to_string()).unwrap();
+}
+
+#[test]
+fn test_import_seals_captures_and_rejects_mismatches() {
+    let temp_dir = TempDir::new().unwrap();
+    let spool_dir = temp_dir.path().join("spool");
+    fs::create_dir_all(&spool_dir).unwrap();
+    let data = pcap();
+    let sha256 = hex::encode(Sha256::digest(&data));
+    let good = "3f1e2d4c-5b6a-4978-8a9b-0c1d2e3f4a5b";
+    let tampered = "4a2b3c4d-5e6f-4a1b-9c2d-3e4f5a6b7c8d";
+    spool(&spool_dir, good, &data, &sha256);
+    spool(&spool_dir, tampered, &data, &sha256);
+    let mut altered = data.clone();
+    altered[40] ^= 0xff;
+    fs::write(spool_dir.join(format!("{}.pcap", tampered)), &altered).unwrap();
+
+    let store = EvidenceStore::new(temp_dir.path().join("store"), None).unwrap();
+    let collector = EvidenceCollector::new("1.0.0", "1.0.0");
+    let outcome = pcap_import::import_captures(&store, &collector, "1.0.0", "1.0.0", &spool_dir).unwrap();
+    assert_eq!(outcome.imported.len(), 1);
+    assert_eq!(outcome.imported[0].capture_id.to_string(), good);
+    assert_eq!(outcome.rejected.len(), 1);
+    assert_eq!(outcome.rejected[0].manifest, tampered);
+
+    // Imported captures leave the spool; rejected ones are kept aside, never sealed
+    assert!(!spool_dir.join(format!("{}.json", good)).exists());
+    assert!(!spool_dir.join(format!("{}.pcap", good)).exists());
+    assert!(spool_dir.join(REJECTED_DIR).join(format!("{}.pcap", tampered)).exists());
+
+    let bundle = store.get_bundle(&outcome.imported[0].bundle_id).unwrap();
+    assert!(bundle.is_sealed);
+    let evidence = &bundle.evidence_items[0];
+    assert_eq!(evidence.source_type, PCAP_SOURCE_TYPE);
+    assert_eq!(evidence.metadata["incident_id"], INCIDENT);
+    assert_eq!(evidence.metadata["detection_id"], DETECTION);
+    assert_eq!(evidence.timestamp.to_rfc3339(), "2026-03-14T08:00:20+00:00");
+    assert_eq!(pcap_import::read_pcap(evidence).unwrap(), data);
+
+    let found = pcap_import::find_capture(&store, &good.to_uppercase()).unwrap();
+    assert_eq!(found.evidence_id, outcome.imported[0].evidence_id);
+    assert!(pcap_import::find_capture(&store, tampered).is_err());
+
+    // A second run finds nothing new
+    let again = pcap_import::import_captures(&store, &collector, "1.0.0", "1.0.0", &spool_dir).unwrap();
+    assert!(again.imported.is_empty() && again.rejected.is_empty());
+}
+
+#[test]
+fn test_read_back_is_verified_and_pcap_left_out_of_reports() {
+    let temp_dir = TempDir::new().unwrap();
+    let spool_dir = temp_dir.path().join("spool");
+    fs::create_dir_all(&spool_dir).unwrap();
+    let data = pcap();
+    let capture_id = "3f1e2d4c-5b6a-4978-8a9b-0c1d2e3f4a5b";
+    spool(&spool_dir, capture_id, &data, &hex::encode(Sha256::digest(&data)));
+
+    let store = EvidenceStore::new(temp_dir.path().join("store"), None).unwrap();
+    let collector = EvidenceCollector::new("1.0.0", "1.0.0");
+    let outcome = pcap_import::import_captures(&store, &collector, "1.0.0", "1.0.0", &spool_dir).unwrap();
+    let bundles = vec![store.get_bundle(&outcome.imported[0].bundle_id).unwrap()];
+
+    let mut evidence = bundles[0].evidence_items[0].clone();
+    evidence.data["pcap_sha256"] = serde_json::Value::String("00".repeat(32));
+    assert!(matches!(pcap_import::read_pcap(&evidence), Err(ReportingError::HashMismatch { .. })));
+
+    let report = ReportBuilder::new("1.0.0", "1.0.0", "build_hash", None)
+        .build_report("Incident Report", "Capture", &bundles, None)
+        .unwrap();
+    let details = report.sections.iter().find(|s| s.title == "Evidence Details").unwrap();
+    let content = &details.subsections[0].content;
+    assert!(content.contains(r#""bpf_filter":"host 10.1.2.3""#));
+    assert!(!content.contains(PCAP_DATA_KEY), "pcap bytes do not belong in the report");
+}
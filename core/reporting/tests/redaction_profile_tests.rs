@@ -0,0 +1,102 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_reporting/tests/redaction_profile_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Redaction profile tests - validates command line, username, IP address and file path handling for the full forensic, SOC and executive profiles, the profile recorded in report metadata and that sealed evidence is unchanged
+
+#![cfg(feature = "future-reporting")]
+
+use reporting::*;
+use tempfile::TempDir;
+use std::collections::HashMap;
+
+fn process_event() -> serde_json::Value {
+    serde_json::json!({
+        "process_data": {
+            "command_line": "vssadmin delete shadows /all /quiet",
+            "executable": "/usr/bin/vssadmin",
+            "username": "alice"
+        },
+        "network_data": { "remote_addr": "203.0.113.7" },
+        "note": "beacon to 198.51.100.20 from /tmp/.x/loader"
+    })
+}
+
+fn build(profile: RedactionProfile) -> (serde_json::Value, EvidenceStore, String, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let store = EvidenceStore::new(temp_dir.path().join("store"), None).unwrap();
+    let collector = EvidenceCollector::new("1.0.0", "1.0.0");
+    let bundle_id = store.create_bundle("1.0.0", "1.0.0").unwrap();
+    let metadata = HashMap::from([("target_user".to_string(), "alice".to_string())]);
+    let evidence = collector.collect("host-1", "process", process_event(), None, metadata).unwrap();
+    store.add_evidence(&bundle_id, evidence).unwrap();
+    store.seal_bundle(&bundle_id).unwrap();
+
+    let bundles = vec![store.get_bundle(&bundle_id).unwrap()];
+    let timeline = ForensicTimeline::from_evidence_bundles(&bundles).unwrap();
+    let report = ReportBuilder::new("1.0.0", "1.0.0", "build_hash", None)
+        .with_redaction(profile)
+        .build_report("Test Report", "Test", &bundles, Some(timeline))
+        .unwrap();
+    (serde_json::to_value(&report).unwrap(), store, bundle_id, temp_dir)
+}
+
+fn evidence_details(report: &serde_json::Value) -> Option<serde_json::Value> {
+    let section = report["sections"].as_array().unwrap().iter().find(|s| s["title"] == "Evidence Details")?;
+    Some(serde_json::from_str(section["subsections"][0]["content"].as_str().unwrap()).unwrap())
+}
+
+#[test]
+fn test_full_forensic_keeps_everything() {
+    let (report, _store, _bundle_id, _dir) = build(RedactionProfile::FullForensic);
+    assert_eq!(report["metadata"]["redaction_profile"], "full_forensic");
+    let details = evidence_details(&report).unwrap();
+    assert_eq!(details["data"], process_event());
+    assert_eq!(details["metadata"]["target_user"], "alice");
+}
+
+#[test]
+fn test_soc_pseudonymizes_usernames_only() {
+    let (report, _store, _bundle_id, _dir) = build(RedactionProfile::Soc);
+    assert_eq!(report["metadata"]["redaction_profile"], "soc");
+    let details = evidence_details(&report).unwrap();
+    let user = details["data"]["process_data"]["username"].as_str().unwrap();
+    assert!(user.starts_with("username:") && user.len() == "username:".len() + 8);
+    // Stable pseudonym: the same user correlates across fields
+    assert_eq!(details["metadata"]["target_user"], user);
+    assert_eq!(details["data"]["process_data"]["command_line"], "vssadmin delete shadows /all /quiet");
+    assert_eq!(details["data"]["network_data"]["remote_addr"], "203.0.113.7");
+    // Timeline metadata is redacted too
+    assert_eq!(report["timeline"]["events"][0]["metadata"]["target_user"], user);
+}
+
+#[test]
+fn test_executive_redacts_and_omits_details() {
+    let (report, store, bundle_id, _dir) = build(RedactionProfile::Executive);
+    assert_eq!(report["metadata"]["redaction_profile"], "executive");
+    assert!(evidence_details(&report).is_none());
+    let text = report.to_string();
+    for secret in ["alice", "203.0.113.7", "vssadmin delete"] {
+        assert!(!text.contains(secret), "{} leaked into executive report", secret);
+    }
+    assert_eq!(report["timeline"]["events"][0]["metadata"]["target_user"], "[REDACTED:username]");
+
+    // Sealed evidence is untouched
+    let bundle = store.get_bundle(&bundle_id).unwrap();
+    assert_eq!(bundle.evidence_items[0].data, process_event());
+}
+
+#[test]
+fn test_free_text_scanning() {
+    let redactor = Redactor::new(RedactionProfile::Executive);
+    assert_eq!(
+        redactor.redact_text("beacon to 198.51.100.20 from /tmp/.x/loader and C:\\Users\\bob\\a.exe"),
+        "beacon to [REDACTED:ip_address] from [REDACTED:file_path] and [REDACTED:file_path]"
+    );
+    // Not addresses or paths
+    assert_eq!(redactor.redact_text("std::net at 12:30:05, 1/2"), "std::net at 12:30:05, 1/2");
+    assert_eq!(redactor.redact_text("fe80::1 reached"), "[REDACTED:ip_address] reached");
+
+    assert_eq!(RedactionProfile::parse("soc"), Some(RedactionProfile::Soc));
+    assert_eq!(RedactionProfile::parse("everyone"), None);
+    assert_eq!(SensitiveField::classify_key("parent_cmdline"), Some(SensitiveField::CommandLine));
+    assert_eq!(SensitiveField::classify_key("zip"), None);
+}
@@ -0,0 +1,229 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_reporting/tests/report_download_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Report download token tests - validates token signing and tampering, expiry, max-use counts persisted across restarts, artifact integrity checks and the chain-of-custody records of downloads
+
+#![cfg(feature = "future-reporting")]
+
+use reporting::*;
+use reporting::distribution::email_body;
+use reporting::download_token::{
+    artifact_path, DownloadConfig, DownloadService, DownloadSigner, DOWNLOAD_PATH, REPORT_DOWNLOADED, REPORT_DOWNLOAD_DENIED,
+};
+use reporting::scheduler::{ExportFormat, ReportArtifact, ScheduledReportConfig};
+use audit::{AuditLogger, AuditSigner, AuditVerifier};
+use chrono::{Duration, Utc};
+use tempfile::TempDir;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+const SECRET: &[u8] = b"0123456789abcdef0123456789abcdef";
+
+fn signer() -> DownloadSigner {
+    DownloadSigner::new(SECRET).unwrap()
+}
+
+/// Write an artifact where the scheduler would and describe it.
+fn artifact(output_dir: &Path, body: &[u8]) -> ReportArtifact {
+    let path = artifact_path(output_dir, "daily-exec", "report-1", "report-1_report.json").unwrap();
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, body).unwrap();
+    ReportArtifact {
+        file_name: "report-1_report.json".to_string(),
+        sha256: EvidenceHasher::new().hash_bytes(body),
+        bytes: body.len() as u64,
+    }
+}
+
+fn issue(artifact: &ReportArtifact, ttl: Duration, max_uses: u32) -> String {
+    signer().issue("daily-exec", "report-1", artifact, ttl, max_uses, Utc::now()).unwrap().1
+}
+
+fn service(output_dir: &Path, log_path: &Path) -> DownloadService {
+    let audit = AuditLogger::new(log_path, AuditSigner::new()).unwrap();
+    DownloadService::new(signer(), output_dir, audit).unwrap()
+}
+
+fn custody_events(log_path: &Path) -> Vec<String> {
+    AuditVerifier::new().replay_log(log_path).unwrap().into_iter().map(|r| r.event_type).collect()
+}
+
+#[test]
+fn test_token_signature_and_tampering() {
+    let temp_dir = TempDir::new().unwrap();
+    let artifact = artifact(temp_dir.path(), b"{}");
+    let token = issue(&artifact, Duration::hours(1), 1);
+
+    let claims = signer().verify(&token).unwrap();
+    assert_eq!(claims.file_name, artifact.file_name);
+    assert_eq!(claims.sha256, artifact.sha256);
+
+    // Any change to the claims invalidates the signature
+    let (body, tag) = token.split_once('.').unwrap();
+    let mut altered = body.to_string();
+    let mid = altered.len() / 2;
+    let swapped = if &altered[mid..mid + 1] == "A" { "B" } else { "A" };
+    altered.replace_range(mid..mid + 1, swapped);
+    assert!(matches!(signer().verify(&format!("{}.{}", altered, tag)), Err(ReportingError::DownloadDenied(_))));
+
+    let other = DownloadSigner::new(b"fedcba9876543210fedcba9876543210").unwrap();
+    assert!(matches!(other.verify(&token), Err(ReportingError::DownloadDenied(_))));
+    assert!(matches!(signer().verify("not-a-token"), Err(ReportingError::DownloadDenied(_))));
+
+    assert!(DownloadSigner::new(b"short").is_err());
+}
+
+#[test]
+fn test_max_uses_are_enforced_and_audited() {
+    let temp_dir = TempDir::new().unwrap();
+    let output_dir = temp_dir.path().join("reports");
+    let log_path = temp_dir.path().join("custody.log");
+    let artifact = artifact(&output_dir, b"{\"report\":1}");
+    let token = issue(&artifact, Duration::hours(1), 2);
+
+    let mut service = service(&output_dir, &log_path);
+    let first = service.redeem(&token, "10.0.0.5:40000", Utc::now()).unwrap();
+    assert_eq!(first.data, b"{\"report\":1}");
+    assert_eq!(first.use_number, 1);
+    assert_eq!(service.redeem(&token, "10.0.0.5:40001", Utc::now()).unwrap().use_number, 2);
+    assert!(matches!(service.redeem(&token, "10.0.0.6:40002", Utc::now()), Err(ReportingError::DownloadDenied(_))));
+
+    assert_eq!(custody_events(&log_path), vec![REPORT_DOWNLOADED, REPORT_DOWNLOADED, REPORT_DOWNLOAD_DENIED]);
+    let records = AuditVerifier::new().replay_log(&log_path).unwrap();
+    assert_eq!(records[0].actor, "10.0.0.5:40000");
+    assert_eq!(records[0].data["token_id"], first.claims.token_id.as_str());
+    assert_eq!(records[0].data["sha256"], artifact.sha256.as_str());
+    assert_eq!(records[0].record_id, first.audit_record_id);
+}
+
+#[test]
+fn test_use_counts_survive_restart() {
+    let temp_dir = TempDir::new().unwrap();
+    let output_dir = temp_dir.path().join("reports");
+    let log_path = temp_dir.path().join("custody.log");
+    let artifact = artifact(&output_dir, b"{}");
+    let token = issue(&artifact, Duration::hours(1), 1);
+
+    service(&output_dir, &log_path).redeem(&token, "peer", Utc::now()).unwrap();
+    let mut reopened = service(&output_dir, &temp_dir.path().join("custody2.log"));
+    assert!(matches!(reopened.redeem(&token, "peer", Utc::now()), Err(ReportingError::DownloadDenied(_))));
+}
+
+#[test]
+fn test_expired_token_is_denied() {
+    let temp_dir = TempDir::new().unwrap();
+    let output_dir = temp_dir.path().join("reports");
+    let log_path = temp_dir.path().join("custody.log");
+    let artifact = artifact(&output_dir, b"{}");
+    let token = issue(&artifact, Duration::hours(1), 5);
+
+    let mut service = service(&output_dir, &log_path);
+    let later = Utc::now() + Duration::hours(2);
+    assert!(matches!(service.redeem(&token, "peer", later), Err(ReportingError::DownloadDenied(_))));
+    assert_eq!(custody_events(&log_path), vec![REPORT_DOWNLOAD_DENIED]);
+}
+
+#[test]
+fn test_altered_artifact_is_not_served() {
+    let temp_dir = TempDir::new().unwrap();
+    let output_dir = temp_dir.path().join("reports");
+    let log_path = temp_dir.path().join("custody.log");
+    let artifact = artifact(&output_dir, b"{\"original\":true}");
+    let token = issue(&artifact, Duration::hours(1), 1);
+
+    let path = artifact_path(&output_dir, "daily-exec", "report-1", &artifact.file_name).unwrap();
+    fs::write(&path, b"{\"original\":false}").unwrap();
+    let mut service = service(&output_dir, &log_path);
+    assert!(matches!(service.redeem(&token, "peer", Utc::now()), Err(ReportingError::HashMismatch { .. })));
+
+    fs::remove_file(&path).unwrap();
+    assert!(matches!(service.redeem(&token, "peer", Utc::now()), Err(ReportingError::MissingEvidence(_))));
+    assert_eq!(custody_events(&log_path), vec![REPORT_DOWNLOAD_DENIED, REPORT_DOWNLOAD_DENIED]);
+}
+
+#[test]
+fn test_forged_token_leaves_no_custody_record() {
+    let temp_dir = TempDir::new().unwrap();
+    let log_path = temp_dir.path().join("custody.log");
+    let mut service = service(temp_dir.path(), &log_path);
+    assert!(matches!(service.redeem("eyJ9.AAAA", "peer", Utc::now()), Err(ReportingError::DownloadDenied(_))));
+    assert!(fs::read_to_string(&log_path).unwrap().is_empty());
+}
+
+#[test]
+fn test_custody_failure_is_a_server_error() {
+    use axum::http::StatusCode;
+    use reporting::download_server::status_for;
+
+    let custody = ReportingError::AuditFailed("chain-of-custody record failed: disk full".to_string());
+    assert_eq!(status_for(&custody), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(status_for(&ReportingError::DownloadDenied("token expired".to_string())), StatusCode::FORBIDDEN);
+    assert_eq!(status_for(&ReportingError::MissingEvidence("report artifact".to_string())), StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn test_artifact_path_rejects_traversal() {
+    let dir = Path::new("/var/lib/ransomeye/reports");
+    assert!(artifact_path(dir, "daily-exec", "..", "x.json").is_err());
+    assert!(artifact_path(dir, "daily-exec", "r", "../../etc/passwd").is_err());
+    assert_eq!(
+        artifact_path(dir, "daily-exec", "r", "r_report.pdf").unwrap(),
+        dir.join("daily-exec").join("r").join("r_report.pdf")
+    );
+}
+
+#[test]
+fn test_scheduled_report_links_replace_attachments() {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var("RANSOMEYE_TEST_REPORT_DOWNLOAD_SECRET", std::str::from_utf8(SECRET).unwrap());
+
+    let store = EvidenceStore::new(temp_dir.path().join("store"), None).unwrap();
+    let bundle_id = store.create_bundle("1.0.0", "1.0.0").unwrap();
+    let evidence = EvidenceCollector::new("1.0.0", "1.0.0")
+        .collect("test_source", "detection", serde_json::json!({"test": "data"}), None, HashMap::new())
+        .unwrap();
+    store.add_evidence(&bundle_id, evidence).unwrap();
+    store.seal_bundle(&bundle_id).unwrap();
+
+    let downloads = DownloadConfig {
+        base_url: "https://reports.example.com/".to_string(),
+        secret_env: "RANSOMEYE_TEST_REPORT_DOWNLOAD_SECRET".to_string(),
+        ttl_secs: 3600,
+        max_uses: 1,
+    };
+    let config = SchedulerConfig {
+        output_dir: temp_dir.path().join("reports"),
+        smtp: None,
+        downloads: Some(downloads),
+        templates: None,
+        reports: vec![ScheduledReportConfig {
+            name: "daily-exec".to_string(),
+            kind: ReportKind::ExecutiveSummary,
+            cron: "@daily".to_string(),
+            window_hours: None,
+            redaction: None,
+            formats: vec![ExportFormat::Json],
+            email: Vec::new(),
+            webhooks: Vec::new(),
+            tenant: None,
+            locale: None,
+            annotations: None,
+        }],
+    };
+    let builder = ReportBuilder::new("1.0.0", "1.0.0", "build_hash", None);
+    let mut scheduler = ReportScheduler::new(config.clone(), temp_dir.path().join("store"), None, builder).unwrap();
+    let run = scheduler.run_now("daily-exec", Utc::now()).unwrap();
+
+    assert_eq!(run.report.downloads.len(), run.report.artifacts.len());
+    let link = &run.report.downloads[0];
+    let prefix = format!("https://reports.example.com{}/", DOWNLOAD_PATH);
+    assert!(link.url.starts_with(&prefix));
+    let body = email_body(&run.report);
+    assert!(body.contains(&link.url));
+    assert!(body.contains("artifacts are not attached"));
+
+    let log_path = temp_dir.path().join("custody.log");
+    let mut service = service(&config.output_dir, &log_path);
+    let download = service.redeem(&link.url[prefix.len()..], "peer", Utc::now()).unwrap();
+    assert_eq!(download.claims.sha256, run.report.artifacts[0].sha256);
+}
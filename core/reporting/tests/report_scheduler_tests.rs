@@ -0,0 +1,183 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_reporting/tests/report_scheduler_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Report scheduler tests - validates cron evaluation, scheduled generation, evidence preservation of generated reports and integrity hashes in distribution messages
+
+#![cfg(feature = "future-reporting")]
+
+use reporting::*;
+use reporting::distribution::{email_body, sign};
+use reporting::scheduler::{ExportFormat, ScheduledReportConfig, SCHEDULED_REPORT_SOURCE_TYPE};
+use chrono::{Duration, TimeZone, Utc};
+use sha2::{Digest, Sha256};
+use tempfile::TempDir;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+fn seal_evidence(store_path: &Path, source_type: &str, stage: Option<&str>) {
+    let store = EvidenceStore::new(store_path, None).unwrap();
+    let collector = EvidenceCollector::new("1.0.0", "1.0.0");
+    let bundle_id = store.create_bundle("1.0.0", "1.0.0").unwrap();
+    let evidence = collector.collect(
+        "test_source",
+        source_type,
+        serde_json::json!({"test": "data"}),
+        stage.map(|s| s.to_string()),
+        HashMap::new(),
+    ).unwrap();
+    store.add_evidence(&bundle_id, evidence).unwrap();
+    store.seal_bundle(&bundle_id).unwrap();
+}
+
+fn report_config(name: &str, kind: ReportKind, cron: &str) -> ScheduledReportConfig {
+    ScheduledReportConfig {
+        name: name.to_string(),
+        kind,
+        cron: cron.to_string(),
+        window_hours: None,
+        redaction: None,
+        formats: vec![ExportFormat::Json, ExportFormat::Csv],
+        email: Vec::new(),
+        webhooks: Vec::new(),
+        tenant: None,
+        locale: None,
+        annotations: None,
+    }
+}
+
+fn scheduler(temp_dir: &TempDir, reports: Vec<ScheduledReportConfig>) -> ReportScheduler {
+    let config = SchedulerConfig {
+        output_dir: temp_dir.path().join("reports"),
+        smtp: None,
+        downloads: None,
+        templates: None,
+        reports,
+    };
+    let builder = ReportBuilder::new("1.0.0", "1.0.0", "build_hash", None);
+    ReportScheduler::new(config, temp_dir.path().join("store"), None, builder).unwrap()
+}
+
+#[test]
+fn test_cron_next_fire_times() {
+    let daily = CronSchedule::parse("0 6 * * *").unwrap();
+    let at = Utc.with_ymd_and_hms(2026, 1, 1, 6, 0, 0).unwrap();
+    assert_eq!(daily.next_after(at), Some(Utc.with_ymd_and_hms(2026, 1, 2, 6, 0, 0).unwrap()));
+
+    // 2026-01-01 is a Thursday; next Monday is 2026-01-05
+    let weekly = CronSchedule::parse("30 7 * * 1").unwrap();
+    assert_eq!(weekly.next_after(at), Some(Utc.with_ymd_and_hms(2026, 1, 5, 7, 30, 0).unwrap()));
+
+    let quarter = CronSchedule::parse("*/15 * * * *").unwrap();
+    let at = Utc.with_ymd_and_hms(2026, 1, 1, 6, 7, 59).unwrap();
+    assert_eq!(quarter.next_after(at), Some(Utc.with_ymd_and_hms(2026, 1, 1, 6, 15, 0).unwrap()));
+
+    // Sunday as 7, and day-of-month OR day-of-week when both are restricted
+    let sunday = CronSchedule::parse("0 0 * * 7").unwrap();
+    // 2026-01-04 is a Sunday
+    assert_eq!(sunday.next_after(at), Some(Utc.with_ymd_and_hms(2026, 1, 4, 0, 0, 0).unwrap()));
+    let either = CronSchedule::parse("0 0 15 * 1").unwrap();
+    assert_eq!(either.next_after(at), Some(Utc.with_ymd_and_hms(2026, 1, 5, 0, 0, 0).unwrap()));
+}
+
+#[test]
+fn test_invalid_cron_rejected() {
+    for expr in ["61 * * * *", "0 0 * *", "0 0 30 2 *", "5-1 * * * *", "*/0 * * * *", "@yearly"] {
+        let result = CronSchedule::parse(expr);
+        assert!(matches!(result, Err(ReportingError::InvalidSchedule(_))), "{} should be rejected", expr);
+    }
+}
+
+#[test]
+fn test_run_now_preserves_report_with_artifact_hashes() {
+    let temp_dir = TempDir::new().unwrap();
+    let store_path = temp_dir.path().join("store");
+    seal_evidence(&store_path, "detection", Some("execution"));
+    seal_evidence(&store_path, "detection", Some("impact"));
+    seal_evidence(&store_path, "network_flow", None);
+
+    let mut scheduler = scheduler(&temp_dir, vec![report_config("weekly-digest", ReportKind::DetectionDigest, "0 7 * * 1")]);
+    let run = scheduler.run_now("weekly-digest", Utc::now()).unwrap();
+
+    // Digest only counts detections
+    assert_eq!(run.report.evidence_items, 2);
+    assert!(run.deliveries.is_empty());
+    assert_eq!(run.report.artifacts.len(), 2);
+    for artifact in &run.report.artifacts {
+        let data = fs::read(run.report.directory.join(&artifact.file_name)).unwrap();
+        assert_eq!(artifact.sha256, hex::encode(Sha256::digest(&data)));
+        assert_eq!(artifact.bytes, data.len() as u64);
+    }
+
+    // The report record is sealed into the evidence store
+    let store = EvidenceStore::new(&store_path, None).unwrap();
+    let bundle = store.get_bundle(&run.report.evidence_bundle_id).unwrap();
+    assert!(bundle.is_sealed);
+    assert_eq!(bundle.bundle_hash, run.report.evidence_bundle_hash);
+    assert_eq!(bundle.evidence_items[0].source_type, SCHEDULED_REPORT_SOURCE_TYPE);
+    assert_eq!(bundle.evidence_items[0].data["report"]["metadata"]["report_id"], run.report.report_id.as_str());
+}
+
+#[test]
+fn test_scheduled_reports_are_not_fed_back() {
+    let temp_dir = TempDir::new().unwrap();
+    seal_evidence(&temp_dir.path().join("store"), "detection", None);
+
+    let mut scheduler = scheduler(&temp_dir, vec![report_config("daily-exec", ReportKind::ExecutiveSummary, "@daily")]);
+    let first = scheduler.run_now("daily-exec", Utc::now()).unwrap();
+    let second = scheduler.run_now("daily-exec", Utc::now()).unwrap();
+    assert_eq!(first.report.evidence_items, 1);
+    assert_eq!(second.report.evidence_items, 1);
+}
+
+#[test]
+fn test_first_sighting_waits_for_next_fire() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut scheduler = scheduler(&temp_dir, vec![report_config("daily-exec", ReportKind::ExecutiveSummary, "0 6 * * *")]);
+
+    let start = Utc.with_ymd_and_hms(2026, 1, 1, 5, 0, 0).unwrap();
+    assert!(scheduler.run_due(start).is_empty());
+    assert!(scheduler.run_due(start + Duration::minutes(30)).is_empty());
+    assert_eq!(scheduler.next_run("daily-exec", start), Some(start + Duration::hours(1)));
+
+    // Two missed days collapse into one catch-up run
+    let runs = scheduler.run_due(start + Duration::days(2));
+    assert_eq!(runs.len(), 1);
+    assert!(scheduler.run_due(start + Duration::days(2)).is_empty());
+}
+
+#[test]
+fn test_config_validation() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut report = report_config("bad name", ReportKind::ExecutiveSummary, "@daily");
+    let config = SchedulerConfig { output_dir: temp_dir.path().to_path_buf(), smtp: None, downloads: None, templates: None, reports: vec![report.clone()] };
+    assert!(config.validate().is_err());
+
+    report.name = "daily".to_string();
+    report.email = vec!["soc@example.com".to_string()];
+    let config = SchedulerConfig { output_dir: temp_dir.path().to_path_buf(), smtp: None, downloads: None, templates: None, reports: vec![report.clone()] };
+    assert!(config.validate().is_err(), "email recipients require an smtp section");
+
+    report.email.clear();
+    report.webhooks = vec![distribution::WebhookTarget { url: "http://reports.example.com/hook".to_string(), secret_env: "X".to_string() }];
+    let config = SchedulerConfig { output_dir: temp_dir.path().to_path_buf(), smtp: None, downloads: None, templates: None, reports: vec![report] };
+    assert!(config.validate().is_err(), "plain http is only allowed to loopback");
+}
+
+#[test]
+fn test_distribution_message_carries_hashes() {
+    let temp_dir = TempDir::new().unwrap();
+    seal_evidence(&temp_dir.path().join("store"), "detection", None);
+    let mut scheduler = scheduler(&temp_dir, vec![report_config("daily-exec", ReportKind::ExecutiveSummary, "@daily")]);
+    let run = scheduler.run_now("daily-exec", Utc::now()).unwrap();
+
+    let body = email_body(&run.report);
+    for artifact in &run.report.artifacts {
+        assert!(body.contains(&format!("{}  {}", artifact.sha256, artifact.file_name)));
+    }
+    assert!(body.contains(&run.report.evidence_bundle_hash));
+    assert!(body.contains("Data residency: unclassified"));
+
+    let signature = sign(b"secret", 1_700_000_000, b"{}");
+    assert!(signature.starts_with("t=1700000000,v1="));
+    assert_eq!(signature.len(), "t=1700000000,v1=".len() + 64);
+}
@@ -0,0 +1,79 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_reporting/tests/residency_classification_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Residency classification tests - validates that reports classify included evidence by residency region and that HTML and CSV exports carry the marking
+
+#![cfg(feature = "future-reporting")]
+
+use reporting::*;
+use tempfile::TempDir;
+use std::collections::HashMap;
+use std::fs;
+
+fn sealed_bundle(store: &EvidenceStore, items: Vec<(serde_json::Value, HashMap<String, String>)>) -> String {
+    let collector = EvidenceCollector::new("1.0.0", "1.0.0");
+    let bundle_id = store.create_bundle("1.0.0", "1.0.0").unwrap();
+    for (data, metadata) in items {
+        let evidence = collector.collect("test_source", "test_type", data, None, metadata).unwrap();
+        store.add_evidence(&bundle_id, evidence).unwrap();
+    }
+    store.seal_bundle(&bundle_id).unwrap();
+    bundle_id
+}
+
+fn region(value: &str) -> HashMap<String, String> {
+    HashMap::from([(RESIDENCY_METADATA_KEY.to_string(), value.to_string())])
+}
+
+#[test]
+fn test_single_region_report() {
+    let temp_dir = TempDir::new().unwrap();
+    let store = EvidenceStore::new(temp_dir.path().join("store"), None).unwrap();
+    let bundle_id = sealed_bundle(&store, vec![
+        (serde_json::json!({"n": 1}), region("eu-west")),
+        // Exported raw_events rows carry the region as a data field
+        (serde_json::json!({"n": 2, "residency_region": "eu-west"}), HashMap::new()),
+    ]);
+
+    let builder = ReportBuilder::new("1.0.0", "1.0.0", "build_hash", None);
+    let report = builder.build_report("Test Report", "Test", &[store.get_bundle(&bundle_id).unwrap()], None).unwrap();
+    assert_eq!(report.summary.residency.regions, vec!["eu-west".to_string()]);
+    assert_eq!(report.summary.residency.unclassified_items, 0);
+    assert_eq!(report.summary.residency.label(), "eu-west");
+}
+
+#[test]
+fn test_mixed_report_is_marked_in_exports() {
+    let temp_dir = TempDir::new().unwrap();
+    let store = EvidenceStore::new(temp_dir.path().join("store"), None).unwrap();
+    let bundle_id = sealed_bundle(&store, vec![
+        (serde_json::json!({"n": 1}), region("us-east")),
+        (serde_json::json!({"n": 2}), region("eu-west")),
+        (serde_json::json!({"n": 3}), HashMap::new()),
+    ]);
+
+    let builder = ReportBuilder::new("1.0.0", "1.0.0", "build_hash", None);
+    let report = builder.build_report("Test Report", "Test", &[store.get_bundle(&bundle_id).unwrap()], None).unwrap();
+    let label = report.summary.residency.label();
+    assert_eq!(label, "multi-region: eu-west, us-east + 1 unclassified item(s)");
+
+    let exporter = ReportExporter::new();
+    let html_path = temp_dir.path().join("report.html");
+    let csv_path = temp_dir.path().join("report.csv");
+    exporter.export_html(&report, &html_path).unwrap();
+    exporter.export_csv(&report, &csv_path).unwrap();
+    assert!(fs::read_to_string(&html_path).unwrap().contains(&label));
+    assert!(fs::read_to_string(&csv_path).unwrap().contains(&label));
+}
+
+#[test]
+fn test_unclassified_evidence() {
+    assert_eq!(ResidencyClassification::default().label(), "unclassified");
+
+    let temp_dir = TempDir::new().unwrap();
+    let store = EvidenceStore::new(temp_dir.path().join("store"), None).unwrap();
+    let bundle_id = sealed_bundle(&store, vec![(serde_json::json!({"n": 1}), region(""))]);
+    let builder = ReportBuilder::new("1.0.0", "1.0.0", "build_hash", None);
+    let report = builder.build_report("Test Report", "Test", &[store.get_bundle(&bundle_id).unwrap()], None).unwrap();
+    assert!(report.summary.residency.regions.is_empty());
+    assert_eq!(report.summary.residency.unclassified_items, 1);
+}
@@ -0,0 +1,129 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_reporting/tests/sandbox_import_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Sandbox report import tests - validates sealing of spooled detonation reports with their verdict and detection links, rejection of reports that do not match their manifest, verified read-back and omission of the raw report from report evidence details
+
+#![cfg(feature = "future-reporting")]
+
+use reporting::sandbox_import::{self, REJECTED_DIR, REPORT_DATA_KEY, SANDBOX_SOURCE_TYPE};
+use reporting::*;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+const AGENT: &str = "5b0c2f4e-9d1a-4c3b-8e7f-6a5d4c3b2a10";
+const DETECTION: &str = "0d9a1e2b-5c3f-4e8a-b7d6-2a4c6e8f0b13";
+const SAMPLE_SHA256: &str = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+
+fn report() -> Vec<u8> {
+    serde_json::to_vec(&serde_json::json!({
+        "info": {"id": 42, "score": 9.2},
+        "signatures": [{"name": "ransomware_file_modifications", "severity": 3}],
+    }))
+    .unwrap()
+}
+
+fn spool(dir: &Path, submission_id: &str, report: &[u8], sha256: &str) {
+    let manifest = serde_json::json!({
+        "submission_id": submission_id,
+        "sha256": SAMPLE_SHA256,
+        "file_path": "/tmp/.x/encryptor",
+        "file_size": 81920,
+        "agent_id": AGENT,
+        "incident_id": null,
+        "source": "agent",
+        "submitted_by": AGENT,
+        "mode": "sample",
+        "sandbox_url": "https://sandbox.example:8090",
+        "sandbox_task_id": 42,
+        "queued_at": "2026-03-14T08:00:00Z",
+        "reported_at": "2026-03-14T08:12:40Z",
+        "score": 9.2,
+        "verdict": "malicious",
+        "detection_id": DETECTION,
+        "report_file": format!("{}.report", submission_id),
+        "report_sha256": sha256,
+        "report_bytes": report.len(),
+    });
+    fs::write(dir.join(format!("{}.report", submission_id)), report).unwrap();
+    fs::write(dir.join(format!("{}.json", submission_id)), manifest.to_string()).unwrap();
+}
+
+#[test]
+fn test_import_seals_reports_and_rejects_mismatches() {
+    let temp_dir = TempDir::new().unwrap();
+    let spool_dir = temp_dir.path().join("spool");
+    fs::create_dir_all(spool_dir.join("samples")).unwrap();
+    fs::write(spool_dir.join("samples").join(SAMPLE_SHA256), b"MZ").unwrap();
+    let data = report();
+    let sha256 = hex::encode(Sha256::digest(&data));
+    let good = "3f1e2d4c-5b6a-4978-8a9b-0c1d2e3f4a5b";
+    let tampered = "4a2b3c4d-5e6f-4a1b-9c2d-3e4f5a6b7c8d";
+    spool(&spool_dir, good, &data, &sha256);
+    spool(&spool_dir, tampered, &data, &sha256);
+    let mut altered = data.clone();
+    altered[10] ^= 0x01;
+    fs::write(spool_dir.join(format!("{}.report", tampered)), &altered).unwrap();
+
+    let store = EvidenceStore::new(temp_dir.path().join("store"), None).unwrap();
+    let collector = EvidenceCollector::new("1.0.0", "1.0.0");
+    let outcome = sandbox_import::import_reports(&store, &collector, "1.0.0", "1.0.0", &spool_dir).unwrap();
+    assert_eq!(outcome.imported.len(), 1);
+    assert_eq!(outcome.imported[0].submission_id.to_string(), good);
+    assert_eq!(outcome.imported[0].verdict, "malicious");
+    assert_eq!(outcome.rejected.len(), 1);
+    assert_eq!(outcome.rejected[0].manifest, tampered);
+
+    // Imported reports leave the spool; rejected ones are kept aside; samples are left alone
+    assert!(!spool_dir.join(format!("{}.json", good)).exists());
+    assert!(!spool_dir.join(format!("{}.report", good)).exists());
+    assert!(spool_dir.join(REJECTED_DIR).join(format!("{}.report", tampered)).exists());
+    assert!(spool_dir.join("samples").join(SAMPLE_SHA256).exists());
+
+    let bundle = store.get_bundle(&outcome.imported[0].bundle_id).unwrap();
+    assert!(bundle.is_sealed);
+    let evidence = &bundle.evidence_items[0];
+    assert_eq!(evidence.source_type, SANDBOX_SOURCE_TYPE);
+    assert_eq!(evidence.metadata["sha256"], SAMPLE_SHA256);
+    assert_eq!(evidence.metadata["verdict"], "malicious");
+    assert_eq!(evidence.metadata["detection_id"], DETECTION);
+    assert_eq!(evidence.metadata["agent_id"], AGENT);
+    assert!(!evidence.metadata.contains_key("incident_id"));
+    assert_eq!(evidence.timestamp.to_rfc3339(), "2026-03-14T08:12:40+00:00");
+    assert_eq!(sandbox_import::read_report(evidence).unwrap(), data);
+
+    let found = sandbox_import::find_report(&store, &good.to_uppercase()).unwrap();
+    assert_eq!(found.evidence_id, outcome.imported[0].evidence_id);
+    assert!(sandbox_import::find_report(&store, tampered).is_err());
+
+    // A second run finds nothing new
+    let again = sandbox_import::import_reports(&store, &collector, "1.0.0", "1.0.0", &spool_dir).unwrap();
+    assert!(again.imported.is_empty() && again.rejected.is_empty());
+}
+
+#[test]
+fn test_read_back_is_verified_and_report_left_out_of_reports() {
+    let temp_dir = TempDir::new().unwrap();
+    let spool_dir = temp_dir.path().join("spool");
+    fs::create_dir_all(&spool_dir).unwrap();
+    let data = report();
+    let submission_id = "3f1e2d4c-5b6a-4978-8a9b-0c1d2e3f4a5b";
+    spool(&spool_dir, submission_id, &data, &hex::encode(Sha256::digest(&data)));
+
+    let store = EvidenceStore::new(temp_dir.path().join("store"), None).unwrap();
+    let collector = EvidenceCollector::new("1.0.0", "1.0.0");
+    let outcome = sandbox_import::import_reports(&store, &collector, "1.0.0", "1.0.0", &spool_dir).unwrap();
+    let bundles = vec![store.get_bundle(&outcome.imported[0].bundle_id).unwrap()];
+
+    let mut evidence = bundles[0].evidence_items[0].clone();
+    evidence.data["report_sha256"] = serde_json::Value::String("00".repeat(32));
+    assert!(matches!(sandbox_import::read_report(&evidence), Err(ReportingError::HashMismatch { .. })));
+
+    let report = ReportBuilder::new("1.0.0", "1.0.0", "build_hash", None)
+        .build_report("Incident Report", "Sandbox", &bundles, None)
+        .unwrap();
+    let details = report.sections.iter().find(|s| s.title == "Evidence Details").unwrap();
+    let content = &details.subsections[0].content;
+    assert!(content.contains(r#""verdict":"malicious""#));
+    assert!(!content.contains(REPORT_DATA_KEY), "the raw sandbox report does not belong in the report");
+}
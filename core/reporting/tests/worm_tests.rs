@@ -0,0 +1,108 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_reporting/tests/worm_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: WORM tests - validates release request rules, fail-closed release authorization and, where the host permits immutable files, locking of sealed evidence and the audited release
+
+#![cfg(feature = "future-reporting")]
+
+use reporting::*;
+use reporting::worm::{self, ReleaseRequest, RELEASE_AUTHORIZED, RELEASE_COMPLETED};
+use audit::{AuditLogger, AuditSigner};
+use tempfile::TempDir;
+use std::collections::HashMap;
+use std::fs;
+
+fn seal(store: &EvidenceStore) -> EvidenceBundleRef {
+    let collector = EvidenceCollector::new("1.0.0", "1.0.0");
+    let bundle_id = store.create_bundle("1.0.0", "1.0.0").unwrap();
+    let evidence = collector.collect("test_source", "test_type", serde_json::json!({"test": "data"}), None, HashMap::new()).unwrap();
+    store.add_evidence(&bundle_id, evidence).unwrap();
+    store.seal_bundle(&bundle_id).unwrap();
+    let bundle_hash = store.get_bundle(&bundle_id).unwrap().bundle_hash;
+    EvidenceBundleRef { bundle_id, bundle_hash }
+}
+
+struct EvidenceBundleRef {
+    bundle_id: String,
+    bundle_hash: String,
+}
+
+fn request(bundle: &EvidenceBundleRef) -> ReleaseRequest {
+    ReleaseRequest {
+        bundle_id: bundle.bundle_id.clone(),
+        bundle_hash: bundle.bundle_hash.clone(),
+        actor: "analyst".to_string(),
+        approver: "counsel".to_string(),
+        reason: "Legal hold LH-2026-014 lifted".to_string(),
+    }
+}
+
+/// WORM needs CAP_LINUX_IMMUTABLE and a filesystem supporting the flag; skip where the host lacks either
+fn worm_supported(dir: &std::path::Path) -> bool {
+    worm::probe(dir).is_ok()
+}
+
+#[test]
+fn test_release_request_requires_two_people_and_reason() {
+    let bundle = EvidenceBundleRef { bundle_id: "b".to_string(), bundle_hash: "h".to_string() };
+
+    assert!(request(&bundle).validate().is_ok());
+
+    let mut same_person = request(&bundle);
+    same_person.approver = "Analyst".to_string();
+    assert!(matches!(same_person.validate(), Err(ReportingError::WormViolation(_))));
+
+    let mut no_reason = request(&bundle);
+    no_reason.reason = "  ".to_string();
+    assert!(matches!(no_reason.validate(), Err(ReportingError::WormViolation(_))));
+}
+
+#[test]
+fn test_release_with_wrong_hash_is_rejected_before_audit() {
+    let temp_dir = TempDir::new().unwrap();
+    let store = EvidenceStore::new(temp_dir.path().join("store"), None).unwrap();
+    let bundle = seal(&store);
+
+    let log_path = temp_dir.path().join("audit.log");
+    let mut audit = AuditLogger::new(&log_path, AuditSigner::new()).unwrap();
+    let mut wrong = request(&bundle);
+    wrong.bundle_hash = "0".repeat(64);
+
+    let result = store.release_worm(&wrong, &mut audit);
+    assert!(matches!(result, Err(ReportingError::HashMismatch { .. })));
+    assert!(fs::read_to_string(&log_path).unwrap().is_empty());
+}
+
+#[test]
+fn test_worm_store_locks_sealed_evidence_until_released() {
+    let temp_dir = TempDir::new().unwrap();
+    let store_path = temp_dir.path().join("store");
+    if !worm_supported(&store_path) {
+        eprintln!("skipping: immutable files not permitted on this host");
+        return;
+    }
+    let options = EvidenceStoreOptions { worm: true, ..EvidenceStoreOptions::default() };
+    let store = EvidenceStore::open(&store_path, None, options).unwrap();
+    let bundle = seal(&store);
+
+    assert!(store.is_worm_locked(&bundle.bundle_id).unwrap());
+    let bundle_file = store_path.join("bundles").join(format!("{}.json", bundle.bundle_id));
+    assert!(fs::write(&bundle_file, b"{}").is_err());
+    assert!(matches!(store.purge_bundle(&bundle.bundle_id), Err(ReportingError::WormViolation(_))));
+
+    let log_path = temp_dir.path().join("audit.log");
+    let mut audit = AuditLogger::new(&log_path, AuditSigner::new()).unwrap();
+    let record = store.release_worm(&request(&bundle), &mut audit).unwrap();
+    assert!(!store.is_worm_locked(&bundle.bundle_id).unwrap());
+
+    let log = fs::read_to_string(&log_path).unwrap();
+    assert!(log.contains(RELEASE_AUTHORIZED));
+    assert!(log.contains(RELEASE_COMPLETED));
+    assert!(log.contains(&record.audit_record_id));
+
+    // Reopening does not re-lock a released bundle, and it can now be purged
+    drop(store);
+    let store = EvidenceStore::open(&store_path, None, options).unwrap();
+    assert!(!store.is_worm_locked(&bundle.bundle_id).unwrap());
+    store.purge_bundle(&bundle.bundle_id).unwrap();
+    assert!(!bundle_file.exists());
+}
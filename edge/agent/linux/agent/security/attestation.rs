@@ -3,14 +3,13 @@
 // Details of functionality of this file: Component attestation for trust verification
 
 use serde::{Serialize, Deserialize};
-use sha2::{Sha256, Digest};
+use crypto::digest::Sha256;
 use hex;
 use tracing::info;
 use base64::{Engine as _, engine::general_purpose};
 
 use crate::errors::AgentError;
 use super::identity::ComponentIdentity;
-use ed25519_dalek::VerifyingKey;
 
 /// Component attestation
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,10 +26,10 @@ pub struct AttestationManager;
 
 impl AttestationManager {
     /// Create attestation for component
-    pub fn create(identity: &ComponentIdentity, verifying_key: &VerifyingKey) -> Result<Attestation, AgentError> {
+    pub fn create(identity: &ComponentIdentity, verifying_key: &[u8; 32]) -> Result<Attestation, AgentError> {
         use std::time::{SystemTime, UNIX_EPOCH};
         
-        let verifying_key_b64 = general_purpose::STANDARD.encode(verifying_key.to_bytes());
+        let verifying_key_b64 = general_purpose::STANDARD.encode(verifying_key);
         
         let timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -2,36 +2,30 @@
 // Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
 // Details of functionality of this file: Ed25519 event signing with replay-safe sequence numbers
 
-use ed25519_dalek::{SigningKey, VerifyingKey, Signature, Signer, Verifier};
-use rand::{rngs::OsRng, RngCore};
 use base64::{Engine as _, engine::general_purpose};
+use crypto::signature::{Ed25519KeyPair, ED25519_PUBLIC_KEY_LEN};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use tracing::{error, debug, info};
 
 use crate::errors::AgentError;
 
-/// Event signer using Ed25519 (ed25519_dalek implementation - supports raw 32-byte seeds)
+/// Event signer using Ed25519 (raw 32-byte seeds) through the build's crypto backend
 pub struct EventSigner {
-    signing_key: SigningKey,
-    verifying_key: VerifyingKey,
+    key_pair: Ed25519KeyPair,
     sequence: Arc<AtomicU64>,
 }
 
 impl EventSigner {
     /// Create new event signer
     pub fn new() -> Result<Self, AgentError> {
-        let mut csprng = OsRng;
-        let mut key_bytes = [0u8; 32];
-        csprng.fill_bytes(&mut key_bytes);
-        let signing_key = SigningKey::from_bytes(&key_bytes);
-        let verifying_key = signing_key.verifying_key();
+        let key_pair = Ed25519KeyPair::generate()
+            .map_err(|e| AgentError::SigningFailed(format!("Failed to generate Ed25519 key: {}", e)))?;
         
         info!("Event signer created with Ed25519 key");
         
         Ok(Self {
-            signing_key,
-            verifying_key,
+            key_pair,
             sequence: Arc::new(AtomicU64::new(0)),
         })
     }
@@ -39,7 +33,6 @@ impl EventSigner {
     /// Load signer from key file (raw 32-byte Ed25519 seed)
     /// 
     /// FAIL-CLOSED: Key must be exactly 32 bytes, valid Ed25519 seed
-    /// Uses ed25519_dalek which supports raw 32-byte seeds directly
     pub fn from_key_file(key_path: &std::path::Path) -> Result<Self, AgentError> {
         let key_bytes = std::fs::read(key_path)
             .map_err(|e| AgentError::SigningFailed(
@@ -52,20 +45,18 @@ impl EventSigner {
             ));
         }
         
-        // Use ed25519_dalek which supports raw 32-byte seeds directly
         let seed_array: [u8; 32] = key_bytes.try_into()
             .map_err(|_| AgentError::SigningFailed(
                 "Failed to convert key bytes to array".to_string()
             ))?;
         
-        let signing_key = SigningKey::from_bytes(&seed_array);
-        let verifying_key = signing_key.verifying_key();
+        let key_pair = Ed25519KeyPair::from_seed(&seed_array)
+            .map_err(|e| AgentError::SigningFailed(format!("Invalid Ed25519 seed: {}", e)))?;
         
         info!("Event signer loaded from key file");
         
         Ok(Self {
-            signing_key,
-            verifying_key,
+            key_pair,
             sequence: Arc::new(AtomicU64::new(0)),
         })
     }
@@ -75,18 +66,33 @@ impl EventSigner {
     /// Includes replay-safe sequence number.
     /// Reuses the initialized signing key - does NOT re-parse the key.
     pub fn sign(&self, data: &[u8]) -> Result<String, AgentError> {
-        let seq = self.sequence.fetch_add(1, Ordering::AcqRel);
-        
+        Ok(self.sign_at(self.reserve_sequence(), data))
+    }
+    
+    /// Take the next sequence number without signing yet. Signatures made later on other
+    /// threads (`sign_at`) then carry the numbers in the order they were reserved.
+    pub fn reserve_sequence(&self) -> u64 {
+        self.sequence.fetch_add(1, Ordering::AcqRel)
+    }
+    
+    /// Sign event data under a sequence number from `reserve_sequence`
+    pub fn sign_at(&self, seq: u64, data: &[u8]) -> String {
         let mut message = Vec::with_capacity(8 + data.len());
         message.extend_from_slice(&seq.to_be_bytes());
         message.extend_from_slice(data);
         
         // Sign using the pre-initialized signing key (no re-parsing)
-        let signature: Signature = self.signing_key.sign(&message);
-        let signature_b64 = general_purpose::STANDARD.encode(signature.to_bytes());
+        let signature = self.key_pair.sign(&message);
+        let signature_b64 = general_purpose::STANDARD.encode(signature);
         
         debug!("Event signed: sequence={}, signature_len={}", seq, signature_b64.len());
-        Ok(signature_b64)
+        signature_b64
+    }
+    
+    /// Sign without a sequence number, for records that carry their own position and are
+    /// verified outside the event stream (sealed log segments)
+    pub fn sign_unsequenced(&self, data: &[u8]) -> String {
+        general_purpose::STANDARD.encode(self.key_pair.sign(data))
     }
     
     /// Verify signature
@@ -113,9 +119,16 @@ impl EventSigner {
         Ok(true)
     }
     
-    /// Get verifying key (public key)
-    pub fn verifying_key(&self) -> VerifyingKey {
-        self.verifying_key
+    /// Derive a 32-byte key for local use (HKDF-SHA256 over the Ed25519 seed). Stable for as long
+    /// as the agent keeps its identity key; `context` separates the uses.
+    pub fn derive_key(&self, context: &[u8]) -> Result<[u8; 32], AgentError> {
+        self.key_pair.derive_key(context)
+            .map_err(|e| AgentError::SigningFailed(format!("Key derivation failed: {}", e)))
+    }
+
+    /// Get verifying key (raw Ed25519 public key)
+    pub fn verifying_key(&self) -> [u8; ED25519_PUBLIC_KEY_LEN] {
+        self.key_pair.public_key()
     }
     
     /// Get current sequence number
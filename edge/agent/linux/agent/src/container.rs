@@ -0,0 +1,367 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_linux_agent/agent/src/container.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Container and Kubernetes context for process events - container id and runtime from /proc/<pid>/cgroup, image from the Docker container config, pod name/namespace/image from the kubelet pod list (optional)
+
+/*
+ * Container Awareness
+ *
+ * The container a process runs in is read from /proc/<pid>/cgroup on every event (pids are
+ * reused, so nothing is cached per pid). The last path segment naming a 64-hex id gives the
+ * container id; its prefix gives the runtime:
+ *   docker-<id>.scope / .../docker/<id>        docker
+ *   cri-containerd-<id>.scope                  containerd
+ *   crio-<id>.scope                            cri-o (crio-conmon-* is the host-side monitor)
+ *   libpod-<id>.scope                          podman
+ * A "pod<uid>" / "-pod<uid>.slice" segment marks a Kubernetes pod.
+ *
+ * Image and container name come from /var/lib/docker/containers/<id>/config.v2.json when Docker
+ * manages the container, and from the kubelet pod list (AGENT_KUBELET_URL, refreshed every
+ * AGENT_KUBELET_REFRESH_SECS) for Kubernetes pods. Processes outside any container carry no
+ * container context: that absence is what marks host activity.
+ *
+ * When the agent itself runs in a container, AGENT_HOST_ROOT points at the host filesystem
+ * (e.g. /host with the host's /proc and /var/lib/docker mounted below it).
+ */
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use parking_lot::{Mutex, RwLock};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use super::errors::AgentError;
+
+/// Docker config lookups remembered (container id -> image, name)
+const MAX_DOCKER_CACHE: usize = 4096;
+
+/// Container context attached to process envelopes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContainerContext {
+    /// Full 64-hex container id
+    pub container_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runtime: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pod_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pod_namespace: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pod_uid: Option<String>,
+}
+
+/// Kubelet view of one container.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PodMetadata {
+    pub pod_name: String,
+    pub pod_namespace: String,
+    pub pod_uid: String,
+    pub container_name: String,
+    pub image: String,
+}
+
+/// Container id, runtime and pod uid from /proc/<pid>/cgroup content.
+pub fn parse_cgroup(content: &str) -> Option<(String, Option<&'static str>, Option<String>)> {
+    for line in content.lines() {
+        let Some(path) = line.splitn(3, ':').nth(2) else { continue };
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        for (i, segment) in segments.iter().enumerate().rev() {
+            let Some((id, runtime)) = container_segment(segment, &segments[..i]) else { continue };
+            let pod_uid = segments[..i].iter().rev().find_map(|s| pod_segment(s));
+            return Some((id, runtime, pod_uid));
+        }
+    }
+    None
+}
+
+fn is_container_id(value: &str) -> bool {
+    value.len() == 64 && value.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+fn container_segment(segment: &str, parents: &[&str]) -> Option<(String, Option<&'static str>)> {
+    let name = segment.strip_suffix(".scope").unwrap_or(segment);
+    if name.starts_with("crio-conmon-") {
+        return None;
+    }
+    const PREFIXES: &[(&str, &str)] =
+        &[("docker-", "docker"), ("cri-containerd-", "containerd"), ("crio-", "cri-o"), ("libpod-", "podman")];
+    for (prefix, runtime) in PREFIXES {
+        if let Some(id) = name.strip_prefix(prefix).filter(|id| is_container_id(id)) {
+            return Some((id.to_ascii_lowercase(), Some(*runtime)));
+        }
+    }
+    // cgroup v1 / cgroupfs driver: bare id under a runtime directory
+    if is_container_id(name) {
+        let runtime = parents.iter().rev().find_map(|p| match *p {
+            "docker" => Some("docker"),
+            "libpod_parent" => Some("podman"),
+            _ => None,
+        });
+        return Some((name.to_ascii_lowercase(), runtime));
+    }
+    None
+}
+
+/// "pod<uid>" (cgroupfs) or "kubepods-besteffort-pod<uid_with_underscores>.slice" (systemd).
+fn pod_segment(segment: &str) -> Option<String> {
+    let name = segment.strip_suffix(".slice").unwrap_or(segment);
+    let uid = match name.rfind("-pod") {
+        Some(i) => &name[i + 4..],
+        None => name.strip_prefix("pod")?,
+    };
+    let uid = uid.replace('_', "-");
+    (uid.len() == 36 && uid.bytes().all(|b| b.is_ascii_hexdigit() || b == b'-')).then_some(uid)
+}
+
+/// Container id -> pod metadata from a kubelet /pods response (PodList).
+pub fn parse_kubelet_pods(pods: &serde_json::Value) -> HashMap<String, PodMetadata> {
+    let mut index = HashMap::new();
+    let Some(items) = pods.get("items").and_then(|i| i.as_array()) else { return index };
+    for pod in items {
+        let meta = &pod["metadata"];
+        let (Some(pod_name), Some(pod_namespace)) = (meta["name"].as_str(), meta["namespace"].as_str()) else {
+            continue;
+        };
+        let pod_uid = meta["uid"].as_str().unwrap_or_default();
+        let statuses = ["initContainerStatuses", "containerStatuses", "ephemeralContainerStatuses"]
+            .iter()
+            .filter_map(|k| pod["status"][*k].as_array())
+            .flatten();
+        for status in statuses {
+            // "containerd://<id>", "docker://<id>", "cri-o://<id>"
+            let Some(id) = status["containerID"].as_str().and_then(|c| c.split("://").nth(1)) else { continue };
+            if !is_container_id(id) {
+                continue;
+            }
+            index.insert(
+                id.to_ascii_lowercase(),
+                PodMetadata {
+                    pod_name: pod_name.to_string(),
+                    pod_namespace: pod_namespace.to_string(),
+                    pod_uid: pod_uid.to_string(),
+                    container_name: status["name"].as_str().unwrap_or_default().to_string(),
+                    image: status["image"].as_str().unwrap_or_default().to_string(),
+                },
+            );
+        }
+    }
+    index
+}
+
+/// Resolves the container context of host pids.
+pub struct ContainerResolver {
+    host_root: PathBuf,
+    /// container id -> (image, name) from Docker's container config
+    docker: Mutex<HashMap<String, (Option<String>, Option<String>)>>,
+    pods: RwLock<HashMap<String, PodMetadata>>,
+}
+
+impl ContainerResolver {
+    pub fn new(host_root: impl AsRef<Path>) -> Self {
+        Self {
+            host_root: host_root.as_ref().to_path_buf(),
+            docker: Mutex::new(HashMap::new()),
+            pods: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Replace the kubelet pod index.
+    pub fn update_pods(&self, pods: HashMap<String, PodMetadata>) {
+        *self.pods.write() = pods;
+    }
+
+    /// Container context of `pid`; `None` for host processes (or a pid that already exited).
+    pub fn resolve(&self, pid: u32) -> Option<ContainerContext> {
+        let cgroup = std::fs::read_to_string(self.host_root.join(format!("proc/{}/cgroup", pid))).ok()?;
+        let (container_id, runtime, pod_uid) = parse_cgroup(&cgroup)?;
+        let mut context = ContainerContext {
+            container_id,
+            runtime: runtime.map(str::to_string),
+            image: None,
+            container_name: None,
+            pod_name: None,
+            pod_namespace: None,
+            pod_uid,
+        };
+        if let Some(pod) = self.pods.read().get(&context.container_id) {
+            context.image = Some(pod.image.clone()).filter(|i| !i.is_empty());
+            context.container_name = Some(pod.container_name.clone()).filter(|n| !n.is_empty());
+            context.pod_name = Some(pod.pod_name.clone());
+            context.pod_namespace = Some(pod.pod_namespace.clone());
+            if !pod.pod_uid.is_empty() {
+                context.pod_uid = Some(pod.pod_uid.clone());
+            }
+        } else if context.runtime.as_deref() == Some("docker") {
+            let (image, name) = self.docker_config(&context.container_id);
+            context.image = image;
+            context.container_name = name;
+        }
+        Some(context)
+    }
+
+    fn docker_config(&self, container_id: &str) -> (Option<String>, Option<String>) {
+        if let Some(cached) = self.docker.lock().get(container_id) {
+            return cached.clone();
+        }
+        let path = self.host_root.join("var/lib/docker/containers").join(container_id).join("config.v2.json");
+        let config: Option<serde_json::Value> =
+            std::fs::read(&path).ok().and_then(|bytes| serde_json::from_slice(&bytes).ok());
+        let Some(config) = config else {
+            // Not cached: the config may not be written yet for a starting container
+            return (None, None);
+        };
+        let image = config["Config"]["Image"].as_str().map(str::to_string);
+        let name = config["Name"].as_str().map(|n| n.trim_start_matches('/').to_string());
+        let mut docker = self.docker.lock();
+        if docker.len() >= MAX_DOCKER_CACHE {
+            docker.clear();
+        }
+        docker.insert(container_id.to_string(), (image.clone(), name.clone()));
+        (image, name)
+    }
+}
+
+/// Kubelet pod list client (AGENT_KUBELET_URL).
+pub struct KubeletClient {
+    client: Client,
+    url: String,
+    token: Option<String>,
+    refresh: Duration,
+    last_refresh: Mutex<Option<Instant>>,
+}
+
+impl KubeletClient {
+    /// `ca_path`: PEM CA for the kubelet serving certificate; `token_path`: bearer token
+    /// (service account token with nodes/proxy read access).
+    pub fn new(url: String, token_path: Option<&str>, ca_path: Option<&str>, refresh: Duration) -> Result<Self, AgentError> {
+        let mut builder = Client::builder().timeout(Duration::from_secs(5));
+        if let Some(ca_path) = ca_path {
+            let pem = std::fs::read(ca_path)
+                .map_err(|e| AgentError::ConfigurationError(format!("Failed to read AGENT_KUBELET_CA_PATH {}: {}", ca_path, e)))?;
+            let ca = reqwest::Certificate::from_pem(&pem)
+                .map_err(|e| AgentError::ConfigurationError(format!("Invalid kubelet CA {}: {}", ca_path, e)))?;
+            builder = builder.add_root_certificate(ca);
+        }
+        let client = builder
+            .build()
+            .map_err(|e| AgentError::ConfigurationError(format!("Failed to create kubelet client: {}", e)))?;
+        let token = match token_path {
+            Some(path) => Some(
+                std::fs::read_to_string(path)
+                    .map_err(|e| AgentError::ConfigurationError(format!("Failed to read AGENT_KUBELET_TOKEN_PATH {}: {}", path, e)))?
+                    .trim()
+                    .to_string(),
+            ),
+            None => None,
+        };
+        Ok(Self { client, url, token, refresh, last_refresh: Mutex::new(None) })
+    }
+
+    /// Whether the pod index is older than the refresh interval.
+    pub fn due(&self) -> bool {
+        self.last_refresh.lock().map_or(true, |at| at.elapsed() >= self.refresh)
+    }
+
+    /// Fetch the node's pods. A failure keeps the previous index; the next attempt waits a full
+    /// refresh interval so an unreachable kubelet cannot stall the event loop.
+    pub async fn fetch(&self) -> Result<HashMap<String, PodMetadata>, AgentError> {
+        *self.last_refresh.lock() = Some(Instant::now());
+        let mut request = self.client.get(&self.url);
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| AgentError::ConfigurationError(format!("kubelet request failed: {}", e)))?;
+        if !response.status().is_success() {
+            warn!("kubelet pod list returned {}", response.status());
+            return Err(AgentError::ConfigurationError(format!("kubelet returned {}", response.status())));
+        }
+        let pods: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| AgentError::ConfigurationError(format!("invalid kubelet pod list: {}", e)))?;
+        let index = parse_kubelet_pods(&pods);
+        debug!("kubelet pod index refreshed: {} containers", index.len());
+        Ok(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const ID: &str = "4f1c0e8a9b7d6c5e3f2a1b0c9d8e7f6a5b4c3d2e1f0a9b8c7d6e5f4a3b2c1d0e";
+    const POD_UID: &str = "0b8f5e2c-7a41-4d3e-9c6b-2f1a8e7d6c5b";
+
+    #[test]
+    fn test_parse_cgroup_runtimes() {
+        let docker = format!("0::/system.slice/docker-{}.scope\n", ID);
+        assert_eq!(parse_cgroup(&docker), Some((ID.to_string(), Some("docker"), None)));
+
+        let v1 = format!("12:pids:/docker/{}\n11:memory:/docker/{}\n", ID, ID);
+        assert_eq!(parse_cgroup(&v1), Some((ID.to_string(), Some("docker"), None)));
+
+        let systemd_k8s = format!(
+            "0::/kubepods.slice/kubepods-burstable.slice/kubepods-burstable-pod{}.slice/cri-containerd-{}.scope\n",
+            POD_UID.replace('-', "_"),
+            ID
+        );
+        assert_eq!(
+            parse_cgroup(&systemd_k8s),
+            Some((ID.to_string(), Some("containerd"), Some(POD_UID.to_string())))
+        );
+
+        let cgroupfs_k8s = format!("4:cpu:/kubepods/besteffort/pod{}/{}\n", POD_UID, ID);
+        assert_eq!(parse_cgroup(&cgroupfs_k8s), Some((ID.to_string(), None, Some(POD_UID.to_string()))));
+
+        assert_eq!(parse_cgroup(&format!("0::/machine.slice/crio-conmon-{}.scope\n", ID)), None);
+        assert_eq!(parse_cgroup("0::/user.slice/user-1000.slice/session-2.scope\n"), None);
+    }
+
+    #[test]
+    fn test_resolve_docker_and_kubelet_metadata() {
+        let root = TempDir::new().unwrap();
+        std::fs::create_dir_all(root.path().join("proc/42")).unwrap();
+        std::fs::write(root.path().join("proc/42/cgroup"), format!("0::/system.slice/docker-{}.scope\n", ID)).unwrap();
+        std::fs::create_dir_all(root.path().join("proc/1")).unwrap();
+        std::fs::write(root.path().join("proc/1/cgroup"), "0::/init.scope\n").unwrap();
+        let config_dir = root.path().join("var/lib/docker/containers").join(ID);
+        std::fs::create_dir_all(&config_dir).unwrap();
+        std::fs::write(config_dir.join("config.v2.json"), r#"{"Name":"/web","Config":{"Image":"nginx:1.25"}}"#).unwrap();
+
+        let resolver = ContainerResolver::new(root.path());
+        assert_eq!(resolver.resolve(1), None);
+        let context = resolver.resolve(42).unwrap();
+        assert_eq!(context.image.as_deref(), Some("nginx:1.25"));
+        assert_eq!(context.container_name.as_deref(), Some("web"));
+        assert_eq!(context.pod_name, None);
+
+        let pods = serde_json::json!({
+            "items": [{
+                "metadata": {"name": "web-7d9f", "namespace": "shop", "uid": POD_UID},
+                "status": {"containerStatuses": [
+                    {"name": "nginx", "image": "registry/nginx:1.25", "containerID": format!("docker://{}", ID)},
+                    {"name": "sidecar", "image": "envoy", "containerID": "docker://not-an-id"}
+                ]}
+            }]
+        });
+        let index = parse_kubelet_pods(&pods);
+        assert_eq!(index.len(), 1);
+        resolver.update_pods(index);
+        let context = resolver.resolve(42).unwrap();
+        assert_eq!(context.pod_name.as_deref(), Some("web-7d9f"));
+        assert_eq!(context.pod_namespace.as_deref(), Some("shop"));
+        assert_eq!(context.pod_uid.as_deref(), Some(POD_UID));
+        assert_eq!(context.image.as_deref(), Some("registry/nginx:1.25"));
+        assert_eq!(context.container_name.as_deref(), Some("nginx"));
+    }
+}
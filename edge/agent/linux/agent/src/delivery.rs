@@ -0,0 +1,633 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_linux_agent/agent/src/delivery.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Telemetry delivery to Core - bounded retries with jittered exponential backoff, retry budget, circuit breaker, spool while Core is unreachable, bandwidth budget and off-peak backlog replay
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use parking_lot::Mutex;
+use rand::Rng;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use tracing::{debug, error, info, warn};
+
+use super::errors::AgentError;
+use super::rate_limit::RateLimiter;
+use super::spool::EventSpool;
+use super::traffic::{BacklogWindows, BandwidthBudget, TelemetryClient};
+
+/// Delivery configuration (built from AgentConfig)
+#[derive(Debug, Clone)]
+pub struct DeliveryConfig {
+    /// Full ingest endpoint, e.g. `http://core:8080/ingest/linux`
+    pub endpoint_url: String,
+    pub api_token: Option<String>,
+    /// Attempts per event including the first (1 = no retries)
+    pub max_attempts: u32,
+    pub backoff_base: Duration,
+    pub backoff_max: Duration,
+    /// Retry budget: at most `retry_budget_tokens` retries in a burst, refilled per second
+    pub retry_budget_tokens: u64,
+    pub retry_budget_refill: u64,
+    /// Consecutive failed attempts before the circuit opens
+    pub circuit_failure_threshold: u32,
+    /// How long the circuit stays open before a single probe is allowed
+    pub circuit_open_duration: Duration,
+    /// Spooled events replayed per successful delivery
+    pub spool_drain_batch: usize,
+    /// Telemetry bandwidth cap in bytes/sec (0 = unlimited), with bursts up to `bandwidth_burst_bytes`
+    pub bandwidth_bytes_per_sec: u64,
+    pub bandwidth_burst_bytes: u64,
+    /// Local-time windows in which the spool backlog is replayed (empty = any time)
+    pub backlog_windows: BacklogWindows,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Delivering normally
+    Closed,
+    /// Core considered unreachable - events go straight to the spool
+    Open,
+    /// Cooldown elapsed - one probe request decides Closed vs Open
+    HalfOpen,
+}
+
+impl CircuitState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CircuitState::Closed => "closed",
+            CircuitState::Open => "open",
+            CircuitState::HalfOpen => "half_open",
+        }
+    }
+}
+
+/// Circuit breaker
+///
+/// Opens after `failure_threshold` consecutive failures, stays open for `open_duration`,
+/// then lets exactly one probe through (half-open). Probe success closes, failure re-opens.
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    open_duration: Duration,
+    inner: Mutex<BreakerInner>,
+}
+
+struct BreakerInner {
+    state: CircuitState,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    probe_in_flight: bool,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, open_duration: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            open_duration,
+            inner: Mutex::new(BreakerInner {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                opened_at: None,
+                probe_in_flight: false,
+            }),
+        }
+    }
+
+    /// Whether a request may be sent now
+    pub fn allow_request(&self) -> bool {
+        let mut inner = self.inner.lock();
+        match inner.state {
+            CircuitState::Closed => true,
+            CircuitState::Open => {
+                let cooled = inner.opened_at.map_or(true, |t| t.elapsed() >= self.open_duration);
+                if cooled {
+                    inner.state = CircuitState::HalfOpen;
+                    inner.probe_in_flight = true;
+                    info!("Delivery circuit half-open: probing Core");
+                }
+                cooled
+            }
+            CircuitState::HalfOpen => {
+                if inner.probe_in_flight {
+                    false
+                } else {
+                    inner.probe_in_flight = true;
+                    true
+                }
+            }
+        }
+    }
+
+    pub fn record_success(&self) {
+        let mut inner = self.inner.lock();
+        if inner.state != CircuitState::Closed {
+            info!("Delivery circuit closed: Core reachable again");
+        }
+        inner.state = CircuitState::Closed;
+        inner.consecutive_failures = 0;
+        inner.opened_at = None;
+        inner.probe_in_flight = false;
+    }
+
+    pub fn record_failure(&self) {
+        let mut inner = self.inner.lock();
+        inner.consecutive_failures = inner.consecutive_failures.saturating_add(1);
+        inner.probe_in_flight = false;
+        let trip = match inner.state {
+            CircuitState::HalfOpen => true,
+            CircuitState::Closed => inner.consecutive_failures >= self.failure_threshold,
+            CircuitState::Open => false,
+        };
+        if trip {
+            inner.state = CircuitState::Open;
+            inner.opened_at = Some(Instant::now());
+            warn!("Delivery circuit OPEN after {} consecutive failures: pausing delivery for {:?}, spooling events",
+                inner.consecutive_failures, self.open_duration);
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        self.inner.lock().state
+    }
+
+    pub fn consecutive_failures(&self) -> u32 {
+        self.inner.lock().consecutive_failures
+    }
+}
+
+/// Jittered exponential backoff before retry number `retry` (0-based).
+///
+/// Ceiling doubles per retry up to `max`; the delay is uniform in [ceiling/2, ceiling]
+/// so agents that failed together do not retry in lockstep, but never retry immediately.
+pub fn backoff_delay(retry: u32, base: Duration, max: Duration) -> Duration {
+    let ceiling = base.saturating_mul(1u32.checked_shl(retry).unwrap_or(u32::MAX)).min(max);
+    let ceiling_ms = ceiling.as_millis() as u64;
+    if ceiling_ms == 0 {
+        return Duration::ZERO;
+    }
+    Duration::from_millis(rand::thread_rng().gen_range(ceiling_ms / 2..=ceiling_ms))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryOutcome {
+    Delivered,
+    /// Not delivered now; kept in the spool for later replay
+    Spooled,
+    /// Core answered with a non-retryable status; the event is not retried
+    Rejected(u16),
+}
+
+enum SendResult {
+    Sent,
+    Rejected(StatusCode, Option<RejectionDetail>),
+    Retryable(String),
+}
+
+/// Validation categories Core reports in a 400 body; anything else is counted as `other`
+const REJECTION_CATEGORIES: [&str; 4] = ["malformed_json", "missing_field", "wrong_type", "invalid_value"];
+
+/// Field-level reason Core gives for a 400 (`envelope_validation_failed` body)
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct RejectionDetail {
+    pub category: String,
+    /// Dotted path in the signed event, e.g. `envelope.data.pid`
+    pub field: String,
+    #[serde(default)]
+    pub expected: String,
+    #[serde(default)]
+    pub message: String,
+    #[serde(default)]
+    pub hint: String,
+}
+
+impl RejectionDetail {
+    /// Parse a 400 body; None for anything but Core's validation details
+    pub fn parse(body: &str) -> Option<Self> {
+        #[derive(Deserialize)]
+        struct Body {
+            error: String,
+            #[serde(flatten)]
+            detail: RejectionDetail,
+        }
+        serde_json::from_str::<Body>(body)
+            .ok()
+            .filter(|b| b.error == "envelope_validation_failed")
+            .map(|b| b.detail)
+    }
+
+    /// Counter key: the category, bounded to the known set
+    pub fn counter_key(&self) -> &str {
+        REJECTION_CATEGORIES
+            .iter()
+            .find(|c| **c == self.category)
+            .copied()
+            .unwrap_or("other")
+    }
+}
+
+/// Delivery manager
+///
+/// Sends signed events to Core. Retryable failures (transport errors, 5xx, 408, 429) are retried
+/// with jittered exponential backoff while the retry budget allows; every failure feeds the
+/// circuit breaker. While the circuit is open nothing is sent and events are spooled; the spool
+/// is drained oldest-first after the next successful delivery, inside the backlog windows only.
+/// Every request body, retries and replay included, waits for the bandwidth budget. A 400 that
+/// carries Core's validation details is logged with the field, expected type and hint, and
+/// counted by category.
+pub struct DeliveryManager {
+    client: TelemetryClient,
+    config: DeliveryConfig,
+    breaker: CircuitBreaker,
+    retry_budget: RateLimiter,
+    bandwidth: Option<BandwidthBudget>,
+    spool: EventSpool,
+    delivered: AtomicU64,
+    retries: AtomicU64,
+    retry_budget_exhausted: AtomicU64,
+    spooled: AtomicU64,
+    rejected: AtomicU64,
+    rejected_by_category: Mutex<BTreeMap<String, u64>>,
+    bandwidth_wait_ms: AtomicU64,
+    backlog_deferred: AtomicU64,
+}
+
+impl DeliveryManager {
+    pub fn new(client: TelemetryClient, config: DeliveryConfig, spool: EventSpool) -> Self {
+        Self {
+            breaker: CircuitBreaker::new(config.circuit_failure_threshold, config.circuit_open_duration),
+            retry_budget: RateLimiter::new(config.retry_budget_tokens, config.retry_budget_refill),
+            bandwidth: BandwidthBudget::new(config.bandwidth_bytes_per_sec, config.bandwidth_burst_bytes),
+            client,
+            config,
+            spool,
+            delivered: AtomicU64::new(0),
+            retries: AtomicU64::new(0),
+            retry_budget_exhausted: AtomicU64::new(0),
+            spooled: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+            rejected_by_category: Mutex::new(BTreeMap::new()),
+            bandwidth_wait_ms: AtomicU64::new(0),
+            backlog_deferred: AtomicU64::new(0),
+        }
+    }
+
+    /// Local spool (for disk budget enforcement)
+    pub fn spool(&self) -> &EventSpool {
+        &self.spool
+    }
+    
+    /// Deliver one signed event body (spooled if Core is unreachable)
+    pub async fn deliver(&self, body: &[u8], event_id: &str) -> Result<DeliveryOutcome, AgentError> {
+        if !self.breaker.allow_request() {
+            debug!("Delivery circuit open, spooling event {}", event_id);
+            return self.spool_event(body);
+        }
+
+        match self.send_with_retry(body, event_id).await {
+            SendResult::Sent => {
+                self.delivered.fetch_add(1, Ordering::Relaxed);
+                info!("POST {} OK | Telemetry delivered: {}", self.config.endpoint_url, event_id);
+                self.drain_spool().await?;
+                Ok(DeliveryOutcome::Delivered)
+            }
+            SendResult::Rejected(status, detail) => {
+                self.record_rejection(event_id, status, detail.as_ref());
+                Ok(DeliveryOutcome::Rejected(status.as_u16()))
+            }
+            SendResult::Retryable(reason) => {
+                warn!("Delivery of event {} failed ({}), spooling", event_id, reason);
+                self.spool_event(body)
+            }
+        }
+    }
+
+    async fn send_with_retry(&self, body: &[u8], event_id: &str) -> SendResult {
+        let mut last_failure = String::new();
+        for attempt in 0..self.config.max_attempts.max(1) {
+            if attempt > 0 {
+                if !self.retry_budget.allow().unwrap_or(false) {
+                    self.retry_budget_exhausted.fetch_add(1, Ordering::Relaxed);
+                    return SendResult::Retryable(format!("retry budget exhausted after: {}", last_failure));
+                }
+                if !self.breaker.allow_request() {
+                    return SendResult::Retryable(format!("circuit open after: {}", last_failure));
+                }
+                let delay = backoff_delay(attempt - 1, self.config.backoff_base, self.config.backoff_max);
+                debug!("Retrying event {} in {:?} (attempt {}/{})", event_id, delay, attempt + 1, self.config.max_attempts);
+                self.retries.fetch_add(1, Ordering::Relaxed);
+                tokio::time::sleep(delay).await;
+            }
+
+            match self.send_once(body).await {
+                SendResult::Retryable(reason) => {
+                    self.breaker.record_failure();
+                    last_failure = reason;
+                }
+                done => {
+                    // Any HTTP answer other than a retryable one means Core is reachable
+                    self.breaker.record_success();
+                    return done;
+                }
+            }
+        }
+        SendResult::Retryable(last_failure)
+    }
+
+    async fn send_once(&self, body: &[u8]) -> SendResult {
+        if let Some(budget) = &self.bandwidth {
+            let waited = budget.acquire(body.len()).await;
+            self.bandwidth_wait_ms.fetch_add(waited.as_millis() as u64, Ordering::Relaxed);
+        }
+        match self.client.post_json(&self.config.endpoint_url, self.config.api_token.as_deref(), body).await {
+            Ok((status, _)) if status.is_success() => SendResult::Sent,
+            Ok((status, _)) if is_retryable(status) => SendResult::Retryable(format!("HTTP {}", status)),
+            Ok((status, body)) => SendResult::Rejected(status, body.as_deref().and_then(RejectionDetail::parse)),
+            Err(e) => SendResult::Retryable(e),
+        }
+    }
+
+    /// Replay spooled events oldest-first, one attempt each; stop at the first failure.
+    /// Outside the backlog windows the spool is left for the next window.
+    async fn drain_spool(&self) -> Result<(), AgentError> {
+        if !self.config.backlog_windows.allows_now() {
+            if !self.spool.is_empty() {
+                self.backlog_deferred.fetch_add(1, Ordering::Relaxed);
+            }
+            return Ok(());
+        }
+        for _ in 0..self.config.spool_drain_batch {
+            let Some((seq, body)) = self.spool.peek_oldest()? else { break };
+            match self.send_once(&body).await {
+                SendResult::Sent => {
+                    self.spool.remove(seq)?;
+                    self.delivered.fetch_add(1, Ordering::Relaxed);
+                }
+                SendResult::Rejected(status, detail) => {
+                    self.spool.remove(seq)?;
+                    self.record_rejection(&format!("spool#{}", seq), status, detail.as_ref());
+                }
+                SendResult::Retryable(reason) => {
+                    self.breaker.record_failure();
+                    warn!("Spool replay paused: {}", reason);
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Spool without attempting delivery (shutdown drain past its grace period)
+    pub fn spool_only(&self, body: &[u8]) -> Result<DeliveryOutcome, AgentError> {
+        self.spool_event(body)
+    }
+
+    fn record_rejection(&self, event_id: &str, status: StatusCode, detail: Option<&RejectionDetail>) {
+        self.rejected.fetch_add(1, Ordering::Relaxed);
+        if let Some(detail) = detail {
+            *self.rejected_by_category.lock().entry(detail.counter_key().to_string()).or_insert(0) += 1;
+        }
+        log_rejection(event_id, status, detail);
+    }
+
+    fn spool_event(&self, body: &[u8]) -> Result<DeliveryOutcome, AgentError> {
+        self.spool.push(body)?;
+        self.spooled.fetch_add(1, Ordering::Relaxed);
+        Ok(DeliveryOutcome::Spooled)
+    }
+
+    /// Get delivery statistics
+    pub fn stats(&self) -> DeliveryStats {
+        DeliveryStats {
+            circuit_state: self.breaker.state(),
+            consecutive_failures: self.breaker.consecutive_failures(),
+            delivered: self.delivered.load(Ordering::Relaxed),
+            retries: self.retries.load(Ordering::Relaxed),
+            retry_budget_exhausted: self.retry_budget_exhausted.load(Ordering::Relaxed),
+            spooled: self.spooled.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+            rejected_by_category: self.rejected_by_category.lock().clone(),
+            spool_depth: self.spool.len(),
+            spool_bytes: self.spool.bytes(),
+            spool_dropped: self.spool.dropped(),
+            bandwidth_wait_ms: self.bandwidth_wait_ms.load(Ordering::Relaxed),
+            backlog_deferred: self.backlog_deferred.load(Ordering::Relaxed),
+        }
+    }
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    status.is_server_error()
+        || status == StatusCode::REQUEST_TIMEOUT
+        || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+fn log_rejection(event_id: &str, status: StatusCode, detail: Option<&RejectionDetail>) {
+    if let Some(d) = detail {
+        error!(
+            "Failed to send event {}: HTTP {} - {} {} (expected {}): {} | hint: {}",
+            event_id, status, d.category, d.field, d.expected, d.message, d.hint
+        );
+    } else if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+        error!("Failed to send event {}: HTTP {} - ingest token missing, expired, revoked, or not bound to this agent", event_id, status);
+    } else if status == StatusCode::MISDIRECTED_REQUEST {
+        error!("Failed to send event {}: HTTP {} - ingest instance is in another residency region; point the agent at an instance in its own region", event_id, status);
+    } else {
+        error!("Failed to send event {}: HTTP {} (not retried)", event_id, status);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DeliveryStats {
+    pub circuit_state: CircuitState,
+    pub consecutive_failures: u32,
+    pub delivered: u64,
+    pub retries: u64,
+    pub retry_budget_exhausted: u64,
+    pub spooled: u64,
+    pub rejected: u64,
+    /// Rejections Core explained with validation details, by category
+    pub rejected_by_category: BTreeMap<String, u64>,
+    pub spool_depth: usize,
+    pub spool_bytes: u64,
+    pub spool_dropped: u64,
+    /// Total time sends waited for the bandwidth budget
+    pub bandwidth_wait_ms: u64,
+    /// Spool replays skipped outside the backlog windows
+    pub backlog_deferred: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_grows_and_is_capped() {
+        let base = Duration::from_millis(100);
+        let max = Duration::from_millis(1000);
+        for _ in 0..100 {
+            let first = backoff_delay(0, base, max);
+            assert!(first >= Duration::from_millis(50) && first <= base);
+            let third = backoff_delay(2, base, max);
+            assert!(third >= Duration::from_millis(200) && third <= Duration::from_millis(400));
+            assert!(backoff_delay(40, base, max) <= max);
+        }
+    }
+
+    #[test]
+    fn test_circuit_opens_after_threshold_and_probes_after_cooldown() {
+        let breaker = CircuitBreaker::new(3, Duration::from_millis(50));
+        for _ in 0..2 {
+            breaker.record_failure();
+        }
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(!breaker.allow_request());
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(breaker.allow_request());
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        // Only one probe at a time
+        assert!(!breaker.allow_request());
+
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(breaker.allow_request());
+        breaker.record_success();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert_eq!(breaker.consecutive_failures(), 0);
+    }
+
+    #[test]
+    fn test_retryable_statuses() {
+        assert!(is_retryable(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(is_retryable(StatusCode::TOO_MANY_REQUESTS));
+        assert!(!is_retryable(StatusCode::UNAUTHORIZED));
+        assert!(!is_retryable(StatusCode::BAD_REQUEST));
+        assert!(!is_retryable(StatusCode::MISDIRECTED_REQUEST));
+    }
+
+    fn unreachable_config() -> DeliveryConfig {
+        DeliveryConfig {
+            // Reserved port on loopback: connection refused
+            endpoint_url: "http://127.0.0.1:9/ingest/linux".to_string(),
+            api_token: None,
+            max_attempts: 2,
+            backoff_base: Duration::from_millis(1),
+            backoff_max: Duration::from_millis(2),
+            retry_budget_tokens: 10,
+            retry_budget_refill: 1,
+            circuit_failure_threshold: 2,
+            circuit_open_duration: Duration::from_secs(60),
+            spool_drain_batch: 10,
+            bandwidth_bytes_per_sec: 0,
+            bandwidth_burst_bytes: 0,
+            backlog_windows: BacklogWindows::default(),
+        }
+    }
+
+    fn manager(dir: &tempfile::TempDir, config: DeliveryConfig) -> DeliveryManager {
+        let spool = EventSpool::open(dir.path(), 1, crate::spool_crypto::SpoolCipher::new(&[7u8; 32])).unwrap();
+        DeliveryManager::new(TelemetryClient::new(None, Duration::from_secs(10)).unwrap(), config, spool)
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_core_spools_and_opens_circuit() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let manager = manager(&dir, unreachable_config());
+        let event = serde_json::to_vec(&serde_json::json!({ "envelope": {}, "payload_hash": "00" })).unwrap();
+
+        assert_eq!(manager.deliver(&event, "e1").await.unwrap(), DeliveryOutcome::Spooled);
+        assert_eq!(manager.deliver(&event, "e2").await.unwrap(), DeliveryOutcome::Spooled);
+
+        let stats = manager.stats();
+        assert_eq!(stats.circuit_state, CircuitState::Open);
+        assert_eq!(stats.retries, 1);
+        assert_eq!(stats.spool_depth, 2);
+        assert_eq!(stats.delivered, 0);
+    }
+
+    #[tokio::test]
+    async fn test_backlog_not_replayed_outside_windows() {
+        let dir = tempfile::TempDir::new().unwrap();
+        // A one-minute window starting two minutes from now: closed for the duration of the test
+        let opens = chrono::Local::now() + chrono::Duration::minutes(2);
+        let window = format!("{}-{}", opens.format("%H:%M"), (opens + chrono::Duration::minutes(1)).format("%H:%M"));
+        let config = DeliveryConfig { backlog_windows: BacklogWindows::parse(&window).unwrap(), ..unreachable_config() };
+        let manager = manager(&dir, config);
+        manager.spool_only(br#"{"envelope":{},"payload_hash":"00"}"#).unwrap();
+
+        manager.drain_spool().await.unwrap();
+
+        let stats = manager.stats();
+        assert_eq!(stats.backlog_deferred, 1);
+        assert_eq!(stats.spool_depth, 1);
+        // Nothing was attempted, so the unreachable endpoint did not count against the circuit
+        assert_eq!(stats.consecutive_failures, 0);
+    }
+
+    #[tokio::test]
+    async fn test_bandwidth_budget_paces_sends() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let config = DeliveryConfig {
+            max_attempts: 1,
+            circuit_failure_threshold: 100,
+            bandwidth_bytes_per_sec: 10_000,
+            bandwidth_burst_bytes: 100,
+            ..unreachable_config()
+        };
+        let manager = manager(&dir, config);
+        let event = serde_json::to_vec(&serde_json::json!({ "envelope": { "pad": "x".repeat(1000) }, "payload_hash": "00" })).unwrap();
+
+        manager.deliver(&event, "e1").await.unwrap();
+        manager.deliver(&event, "e2").await.unwrap();
+
+        // Two ~1 KB bodies against a 100 byte burst at 10 KB/s wait at least ~100 ms in total
+        assert!(manager.stats().bandwidth_wait_ms >= 100);
+    }
+
+    #[tokio::test]
+    async fn test_validation_rejections_counted_by_category() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Core stand-in: answers every request with a validation 400
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let body = r#"{"error":"envelope_validation_failed","category":"wrong_type","field":"envelope.data.pid","expected":"u64","message":"invalid type: string \"1\", expected u64","hint":"Send envelope.data.pid as u64"}"#;
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = vec![0u8; 64 * 1024];
+                let _ = stream.read(&mut buf).await;
+                let response = format!(
+                    "HTTP/1.1 400 Bad Request\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let config = DeliveryConfig { endpoint_url: format!("http://{}/ingest/linux", addr), ..unreachable_config() };
+        let manager = manager(&dir, config);
+        let event = serde_json::to_vec(&serde_json::json!({ "envelope": { "data": { "pid": "1" } }, "payload_hash": "00" })).unwrap();
+
+        assert_eq!(manager.deliver(&event, "e1").await.unwrap(), DeliveryOutcome::Rejected(400));
+        assert_eq!(manager.deliver(&event, "e2").await.unwrap(), DeliveryOutcome::Rejected(400));
+
+        let stats = manager.stats();
+        assert_eq!(stats.rejected, 2);
+        assert_eq!(stats.rejected_by_category.get("wrong_type"), Some(&2));
+        // A rejection is an answer from Core, not a failure
+        assert_eq!(stats.circuit_state, CircuitState::Closed);
+        assert_eq!(stats.spool_depth, 0);
+
+        // Unknown categories stay bounded; other 400 bodies carry no detail
+        let detail = RejectionDetail::parse(r#"{"error":"envelope_validation_failed","category":"new_kind","field":"x"}"#).unwrap();
+        assert_eq!(detail.counter_key(), "other");
+        assert!(RejectionDetail::parse(r#"{"error":"bad_request"}"#).is_none());
+        assert!(RejectionDetail::parse("Bad Request").is_none());
+    }
+}
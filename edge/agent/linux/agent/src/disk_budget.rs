@@ -0,0 +1,109 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_linux_agent/agent/src/disk_budget.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Global disk-usage budget across the agent's spool and managed log files
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::warn;
+
+use super::logfile::RotatingLogFile;
+use super::spool::EventSpool;
+
+/// Disk budget
+///
+/// One byte budget shared by everything the agent writes locally. Enforcement, in order:
+/// 1. compressed log archives are pruned (oldest first) while logs use more than half the budget,
+/// 2. the spool is bounded to whatever the logs leave (oldest segments dropped).
+/// Telemetry keeps at least half of the budget. Reaching the budget is reported as unhealthy
+/// instead of writing past it.
+pub struct DiskBudget {
+    budget_bytes: u64,
+    /// Spool drop counter at the previous enforcement
+    last_dropped: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiskUsage {
+    pub spool_bytes: u64,
+    pub log_bytes: u64,
+    pub budget_bytes: u64,
+    /// Budget reached: the budget made the spool shed events since the last check, or logs
+    /// still exceed their share after pruning
+    pub exceeded: bool,
+}
+
+impl DiskBudget {
+    pub fn new(budget_mb: u64) -> Self {
+        Self { budget_bytes: budget_mb * 1024 * 1024, last_dropped: AtomicU64::new(0) }
+    }
+
+    /// Apply the budget and report usage after enforcement
+    pub fn enforce(&self, spool: &EventSpool, logs: Option<&RotatingLogFile>) -> DiskUsage {
+        let log_share = self.budget_bytes / 2;
+        let mut log_bytes = 0;
+        if let Some(logs) = logs {
+            if logs.usage_bytes() > log_share {
+                let removed = logs.prune_to(log_share);
+                if removed > 0 {
+                    warn!("Disk budget: pruned {} compressed log archives", removed);
+                }
+            }
+            log_bytes = logs.usage_bytes();
+        }
+
+        let spool_limit = self.budget_bytes.saturating_sub(log_bytes);
+        spool.set_limit_bytes(spool_limit);
+        let dropped = spool.dropped();
+        let new_drops = dropped > self.last_dropped.swap(dropped, Ordering::Relaxed);
+
+        DiskUsage {
+            spool_bytes: spool.bytes(),
+            log_bytes,
+            budget_bytes: self.budget_bytes,
+            exceeded: log_bytes > log_share || (new_drops && spool_limit < spool.max_bytes()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spool_crypto::SpoolCipher;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_budget_bounds_spool_and_prunes_logs() {
+        let dir = TempDir::new().unwrap();
+        let spool = EventSpool::open(&dir.path().join("spool"), 8, SpoolCipher::new(&[7u8; 32])).unwrap();
+        let logs = RotatingLogFile::open(&dir.path().join("log").join("agent.log"), 64 * 1024, 100).unwrap();
+        // Incompressible log lines so archives keep their size
+        let mut x = 0x2545_F491_4F6C_DD1Du64;
+        let line: Vec<u8> = (0..60 * 1024)
+            .map(|_| {
+                x ^= x << 13;
+                x ^= x >> 7;
+                x ^= x << 17;
+                x as u8
+            })
+            .collect();
+        for _ in 0..20 {
+            (&logs).write_all(&line).unwrap();
+        }
+
+        let budget = DiskBudget::new(1);
+        let usage = budget.enforce(&spool, Some(&logs));
+        assert!(usage.log_bytes <= 512 * 1024 + 64 * 1024);
+        assert!(!usage.exceeded);
+
+        for _ in 0..100 {
+            let _ = spool.push(&line);
+        }
+        let usage = budget.enforce(&spool, Some(&logs));
+        assert!(usage.spool_bytes + usage.log_bytes <= 1024 * 1024);
+        assert!(spool.dropped() > 0);
+        assert!(usage.exceeded);
+
+        // No further shedding: budget no longer reported as exceeded
+        assert!(!budget.enforce(&spool, Some(&logs)).exceeded);
+    }
+}
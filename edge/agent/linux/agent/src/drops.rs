@@ -0,0 +1,120 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_linux_agent/agent/src/drops.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Dropped-event ledger - cumulative per-reason drop counters for this agent run, reported to Core in agent_stats
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Why an event never reached Core (spool evictions are counted by the spool itself)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropReason {
+    /// Rate limiter refused the event at the source
+    RateLimited = 0,
+    /// Shed by backpressure admission before the delivery queue
+    Backpressure = 1,
+    /// Dropped or evicted by the full priority queue
+    QueueFull = 2,
+    /// Signing channel full (agent_stats reports)
+    ChannelFull = 3,
+    /// Neither delivered nor spooled
+    SpoolFailed = 4,
+}
+
+const REASONS: usize = 5;
+
+/// Cumulative drops per reason for one run (serialized as data.agent_stats.dropped_by_reason)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DropsByReason {
+    pub rate_limited: u64,
+    pub backpressure: u64,
+    pub queue_full: u64,
+    pub channel_full: u64,
+    /// Spooled events evicted to stay within the spool bound
+    pub spool_evicted: u64,
+    pub spool_failed: u64,
+}
+
+impl DropsByReason {
+    pub fn total(&self) -> u64 {
+        self.rate_limited + self.backpressure + self.queue_full + self.channel_full + self.spool_evicted + self.spool_failed
+    }
+}
+
+/// Per-reason drop counters since agent start
+///
+/// Counters are cumulative for `run_id`, which is new for every start: Core adds only the increase
+/// since the last report of the same run, so a lost or replayed report never skews the daily totals.
+pub struct DropLedger {
+    run_id: String,
+    dropped: [AtomicU64; REASONS],
+}
+
+impl Default for DropLedger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DropLedger {
+    pub fn new() -> Self {
+        Self {
+            run_id: Uuid::new_v4().to_string(),
+            dropped: Default::default(),
+        }
+    }
+
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    pub fn record(&self, reason: DropReason) {
+        self.dropped[reason as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Current counters; `spool_evicted` comes from the spool (cumulative since start)
+    pub fn snapshot(&self, spool_evicted: u64) -> DropsByReason {
+        let get = |r: DropReason| self.dropped[r as usize].load(Ordering::Relaxed);
+        DropsByReason {
+            rate_limited: get(DropReason::RateLimited),
+            backpressure: get(DropReason::Backpressure),
+            queue_full: get(DropReason::QueueFull),
+            channel_full: get(DropReason::ChannelFull),
+            spool_evicted,
+            spool_failed: get(DropReason::SpoolFailed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_is_cumulative_per_reason() {
+        let ledger = DropLedger::new();
+        ledger.record(DropReason::RateLimited);
+        ledger.record(DropReason::QueueFull);
+        ledger.record(DropReason::QueueFull);
+
+        let drops = ledger.snapshot(4);
+        assert_eq!(drops, DropsByReason { rate_limited: 1, queue_full: 2, spool_evicted: 4, ..Default::default() });
+        assert_eq!(drops.total(), 7);
+
+        ledger.record(DropReason::SpoolFailed);
+        assert_eq!(ledger.snapshot(4).total(), 8);
+    }
+
+    #[test]
+    fn test_reasons_serialize_as_flat_counter_map() {
+        let json = serde_json::to_value(DropLedger::new().snapshot(0)).unwrap();
+        let keys: Vec<_> = json.as_object().unwrap().keys().cloned().collect();
+        assert_eq!(keys.len(), 6);
+        assert!(json.as_object().unwrap().values().all(|v| v.as_u64() == Some(0)));
+    }
+
+    #[test]
+    fn test_run_id_is_new_per_ledger() {
+        assert_ne!(DropLedger::new().run_id(), DropLedger::new().run_id());
+    }
+}
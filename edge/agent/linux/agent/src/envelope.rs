@@ -6,12 +6,22 @@ use serde::{Serialize, Deserialize};
 use chrono::Utc;
 use tracing::debug;
 use uuid::Uuid;
+use crypto::digest::Sha256;
+use ::health::HealthReport;
 
 use super::errors::AgentError;
 use super::process::ProcessEvent;
+use super::lineage::LineageLink;
 use super::filesystem::FilesystemEvent;
 use super::network::NetworkEvent;
 use super::features::Features;
+use super::priority::DropCounters;
+use super::drops::DropsByReason;
+use super::kernel_caps::KernelCapabilities;
+use super::inventory::HostInventory;
+use super::container::ContainerContext;
+use super::yara_scan::{YaraMatchData, YaraScanStatus};
+use super::hash_policy::{ExecHash, HashPolicyMatch};
 
 /// Phase-4 event envelope
 /// 
@@ -29,6 +39,56 @@ pub struct EventEnvelope {
     pub data: EventData,
 }
 
+impl EventEnvelope {
+    /// Attach the container context of the event's process (`None` keeps it host activity)
+    pub fn with_container(mut self, container: Option<ContainerContext>) -> Self {
+        self.data.container = container;
+        self
+    }
+    
+    /// Attach the hash of the executed binary and its allowlist/blocklist verdict
+    pub fn with_exec_hash(mut self, exec_hash: Option<ExecHash>) -> Self {
+        if let (Some(process), Some(exec_hash)) = (self.data.process_data.as_mut(), exec_hash) {
+            process.sha256 = Some(exec_hash.sha256);
+            process.hash_policy = exec_hash.policy_match;
+        }
+        self
+    }
+
+    /// Canonical JSON bytes (struct field order) covered by payload_hash and the delivery signature
+    pub fn canonical_bytes(&self) -> Result<Vec<u8>, AgentError> {
+        serde_json::to_vec(self)
+            .map_err(|e| AgentError::EnvelopeCreationFailed(format!("Failed to serialize envelope: {}", e)))
+    }
+}
+
+/// SHA-256 of canonical envelope bytes; hex encoded it is the SignedEvent payload_hash
+pub fn payload_digest(canonical_bytes: &[u8]) -> [u8; 32] {
+    Sha256::digest(canonical_bytes)
+}
+
+/// SignedEvent body for /ingest/linux. The canonical bytes are embedded verbatim as "envelope",
+/// so the envelope Core receives and hashes is exactly the one payload_hash covers.
+pub fn signed_event_body(canonical_bytes: &[u8], fields: &serde_json::Value) -> Result<Vec<u8>, AgentError> {
+    let fields = serde_json::to_vec(fields)
+        .map_err(|e| AgentError::EnvelopeCreationFailed(format!("Failed to serialize signed event: {}", e)))?;
+    // fields is a JSON object: splice "envelope" in ahead of its first member
+    let members = match fields.as_slice() {
+        [b'{', b'}'] => &[][..],
+        [b'{', members @ .., b'}'] => members,
+        _ => return Err(AgentError::EnvelopeCreationFailed("Signed event fields must be a JSON object".to_string())),
+    };
+    let mut body = Vec::with_capacity(canonical_bytes.len() + fields.len() + 16);
+    body.extend_from_slice(b"{\"envelope\":");
+    body.extend_from_slice(canonical_bytes);
+    if !members.is_empty() {
+        body.push(b',');
+        body.extend_from_slice(members);
+    }
+    body.push(b'}');
+    Ok(body)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EventData {
     pub event_category: String,
@@ -39,6 +99,15 @@ pub struct EventData {
     pub filesystem_data: Option<FilesystemData>,
     pub network_data: Option<NetworkData>,
     pub features: FeaturesData,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent_stats: Option<AgentStatsData>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host_inventory: Option<HostInventory>,
+    /// Container the process runs in; absent for host activity
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container: Option<ContainerContext>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub yara_match: Option<YaraMatchData>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -49,6 +118,15 @@ pub struct ProcessData {
     pub command_line: Option<String>,
     pub mmap_address: Option<u64>,
     pub mmap_size: Option<u64>,
+    /// Per-boot process lineage link; Core verifies the chain per (agent, boot_id, chain_id)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lineage: Option<LineageLink>,
+    /// SHA-256 of the executed binary (exec events, with a hash policy)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Allowlist/blocklist verdict; absent for unlisted binaries
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash_policy: Option<HashPolicyMatch>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -71,6 +149,36 @@ pub struct NetworkData {
     pub local_addr: Option<String>,
     pub local_port: Option<u16>,
     pub bytes_transferred: Option<u64>,
+    /// Network namespace inode of the socket; pairs with `container` to attribute the connection
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub netns_inode: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host_netns: Option<bool>,
+}
+
+/// Periodic agent self-report (event_category "agent_stats")
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentStatsData {
+    /// Cumulative events shed per priority since agent start
+    pub dropped_by_priority: DropCounters,
+    pub queue_depth: usize,
+    pub spool_depth: usize,
+    pub circuit_state: String,
+    /// eBPF/auditd source per hook on this kernel (see kernel_caps.rs)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kernel_capabilities: Option<KernelCapabilities>,
+    /// New per agent start; the drop counters below are cumulative within it
+    #[serde(default)]
+    pub run_id: String,
+    /// Cumulative events lost per reason in this run (see drops.rs)
+    #[serde(default)]
+    pub dropped_by_reason: DropsByReason,
+    /// Installed YARA rule pack and last scan; absent when scanning is off
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub yara: Option<YaraScanStatus>,
+    /// Subsystem health in the shared model (see health.rs)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health: Option<HealthReport>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -129,6 +237,9 @@ impl EnvelopeBuilder {
                     command_line: event.command_line.clone(),
                     mmap_address: event.mmap_address,
                     mmap_size: event.mmap_size,
+                    lineage: Some(event.lineage.clone()),
+                    sha256: None,
+                    hash_policy: None,
                 }),
                 filesystem_data: None,
                 network_data: None,
@@ -140,6 +251,10 @@ impl EnvelopeBuilder {
                     process_activity: features.process_activity,
                     filesystem_activity: features.filesystem_activity,
                 },
+                agent_stats: None,
+                host_inventory: None,
+                container: None,
+                yara_match: None,
             },
         };
         
@@ -189,6 +304,10 @@ impl EnvelopeBuilder {
                     process_activity: features.process_activity,
                     filesystem_activity: features.filesystem_activity,
                 },
+                agent_stats: None,
+                host_inventory: None,
+                container: None,
+                yara_match: None,
             },
         };
         
@@ -231,6 +350,8 @@ impl EnvelopeBuilder {
                     local_addr: event.local_addr.clone(),
                     local_port: event.local_port,
                     bytes_transferred: event.bytes_transferred,
+                    netns_inode: event.netns_inode,
+                    host_netns: event.host_netns,
                 }),
                 features: FeaturesData {
                     event_type: features.event_type.clone(),
@@ -240,6 +361,10 @@ impl EnvelopeBuilder {
                     process_activity: features.process_activity,
                     filesystem_activity: features.filesystem_activity,
                 },
+                agent_stats: None,
+                host_inventory: None,
+                container: None,
+                yara_match: None,
             },
         };
         
@@ -247,9 +372,190 @@ impl EnvelopeBuilder {
         Ok(envelope)
     }
     
+    /// Create Phase-4 event envelope carrying periodic agent statistics
+    pub fn build_agent_stats(&mut self, pid: u32, stats: AgentStatsData, signature: String) -> Result<EventEnvelope, AgentError> {
+        self.sequence += 1;
+        
+        let event_id = Uuid::new_v4().to_string();
+        let timestamp = Utc::now().to_rfc3339();
+        
+        let envelope = EventEnvelope {
+            event_id,
+            timestamp,
+            component: self.component.clone(),
+            component_id: self.component_id.clone(),
+            event_type: "agent_stats".to_string(),
+            sequence: self.sequence,
+            signature,
+            data: EventData {
+                event_category: "agent_stats".to_string(),
+                pid,
+                uid: 0,
+                gid: 0,
+                process_data: None,
+                filesystem_data: None,
+                network_data: None,
+                features: FeaturesData {
+                    event_type: "agent_stats".to_string(),
+                    syscall_number: None,
+                    path_count: 0,
+                    network_activity: false,
+                    process_activity: false,
+                    filesystem_activity: false,
+                },
+                agent_stats: Some(stats),
+                host_inventory: None,
+                container: None,
+                yara_match: None,
+            },
+        };
+        
+        debug!("Created agent stats envelope: {}", envelope.event_id);
+        Ok(envelope)
+    }
+    
+    /// Create Phase-4 event envelope carrying the host inventory (startup and daily)
+    pub fn build_host_inventory(&mut self, pid: u32, inventory: HostInventory, signature: String) -> Result<EventEnvelope, AgentError> {
+        self.sequence += 1;
+        
+        let envelope = EventEnvelope {
+            event_id: Uuid::new_v4().to_string(),
+            timestamp: Utc::now().to_rfc3339(),
+            component: self.component.clone(),
+            component_id: self.component_id.clone(),
+            event_type: "host_inventory".to_string(),
+            sequence: self.sequence,
+            signature,
+            data: EventData {
+                event_category: "host_inventory".to_string(),
+                pid,
+                uid: 0,
+                gid: 0,
+                process_data: None,
+                filesystem_data: None,
+                network_data: None,
+                features: FeaturesData {
+                    event_type: "host_inventory".to_string(),
+                    syscall_number: None,
+                    path_count: 0,
+                    network_activity: false,
+                    process_activity: false,
+                    filesystem_activity: false,
+                },
+                agent_stats: None,
+                host_inventory: Some(inventory),
+                container: None,
+                yara_match: None,
+            },
+        };
+        
+        debug!("Created host inventory envelope: {}", envelope.event_id);
+        Ok(envelope)
+    }
+    
+    /// Create Phase-4 event envelope for a YARA rule match (high severity)
+    pub fn build_yara_match(&mut self, pid: u32, hit: YaraMatchData, signature: String) -> Result<EventEnvelope, AgentError> {
+        self.sequence += 1;
+        
+        let envelope = EventEnvelope {
+            event_id: Uuid::new_v4().to_string(),
+            timestamp: Utc::now().to_rfc3339(),
+            component: self.component.clone(),
+            component_id: self.component_id.clone(),
+            event_type: "yara_match".to_string(),
+            sequence: self.sequence,
+            signature,
+            data: EventData {
+                event_category: "yara_match".to_string(),
+                pid,
+                uid: 0,
+                gid: 0,
+                process_data: None,
+                filesystem_data: None,
+                network_data: None,
+                features: FeaturesData {
+                    event_type: "yara_match".to_string(),
+                    syscall_number: None,
+                    path_count: 1,
+                    network_activity: false,
+                    process_activity: false,
+                    filesystem_activity: true,
+                },
+                agent_stats: None,
+                host_inventory: None,
+                container: None,
+                yara_match: Some(hit),
+            },
+        };
+        
+        debug!("Created YARA match envelope: {}", envelope.event_id);
+        Ok(envelope)
+    }
+    
     /// Get current sequence number
     pub fn sequence(&self) -> u64 {
         self.sequence
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_golden_envelopes_round_trip_byte_for_byte() {
+        for golden in envelope_fixtures::for_producer("linux_agent") {
+            let envelope: EventEnvelope = serde_json::from_str(golden.envelope)
+                .unwrap_or_else(|e| panic!("{}: agent no longer reads v{} envelopes: {}", golden.name, golden.schema_version, e));
+            let canonical = envelope.canonical_bytes().unwrap();
+            assert_eq!(String::from_utf8(canonical.clone()).unwrap(), golden.envelope, "{}: canonical bytes changed", golden.name);
+            assert_eq!(hex::encode(payload_digest(&canonical)), golden.payload_hash(), "{}", golden.name);
+        }
+    }
+
+    #[test]
+    fn test_golden_signed_event_carries_canonical_bytes() {
+        for golden in envelope_fixtures::for_producer("linux_agent") {
+            let envelope: EventEnvelope = serde_json::from_str(golden.envelope).unwrap();
+            let canonical = envelope.canonical_bytes().unwrap();
+            let body = signed_event_body(&canonical, &serde_json::json!({ "payload_hash": golden.payload_hash(), "sequence": 1 })).unwrap();
+
+            // Core stores and hashes the envelope as received: the canonical bytes, so its hash is payload_hash
+            let prefix = b"{\"envelope\":";
+            assert_eq!(&body[prefix.len()..prefix.len() + canonical.len()], canonical.as_slice(), "{}", golden.name);
+            assert_eq!(hex::encode(Sha256::digest(&canonical)), golden.envelope_sha256(), "{}", golden.name);
+
+            let parsed: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(parsed["payload_hash"], golden.payload_hash(), "{}", golden.name);
+            assert_eq!(parsed["sequence"], 1, "{}", golden.name);
+        }
+    }
+
+    #[test]
+    fn test_golden_field_semantics() {
+        for golden in envelope_fixtures::for_producer("linux_agent") {
+            let envelope: EventEnvelope = serde_json::from_str(golden.envelope).unwrap();
+            let expected = golden.expected();
+            assert_eq!(envelope.event_id, expected["event_id"].as_str().unwrap(), "{}", golden.name);
+            assert_eq!(envelope.timestamp, expected["observed_at"].as_str().unwrap(), "{}", golden.name);
+            assert_eq!(envelope.component_id, expected["component_id"].as_str().unwrap(), "{}", golden.name);
+            assert_eq!(envelope.data.event_category, expected["event_category"].as_str().unwrap(), "{}", golden.name);
+            assert_eq!(envelope.data.pid as u64, expected["pid"].as_u64().unwrap(), "{}", golden.name);
+            assert_eq!(
+                envelope.data.process_data.as_ref().and_then(|p| p.executable.as_deref()),
+                expected["process_name"].as_str(),
+                "{}",
+                golden.name
+            );
+            assert_eq!(envelope.data.filesystem_data.as_ref().map(|f| f.path.as_str()), expected["file_path"].as_str(), "{}", golden.name);
+            assert_eq!(
+                envelope.data.network_data.as_ref().and_then(|n| n.remote_addr.as_deref()),
+                expected["network_src_ip"].as_str(),
+                "{}",
+                golden.name
+            );
+            assert_eq!(envelope.data.agent_stats.as_ref().map(|s| s.run_id.as_str()), expected["run_id"].as_str(), "{}", golden.name);
+        }
+    }
+}
@@ -56,5 +56,17 @@ pub enum AgentError {
     
     #[error("Audit error: {0}")]
     AuditError(String),
+    
+    #[error("Spool error: {0}")]
+    SpoolError(String),
+    
+    #[error("Pipeline failed: {0}")]
+    PipelineFailed(String),
+    
+    #[error("Memory acquisition failed: {0}")]
+    MemoryAcquisitionFailed(String),
+    
+    #[error("YARA scan failed: {0}")]
+    YaraScanFailed(String),
 }
 
@@ -8,7 +8,7 @@ use std::sync::atomic::{AtomicU64, AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::thread;
-use sha2::{Sha256, Digest};
+use crypto::digest::Sha256;
 use hex;
 use tracing::{error, warn, info};
 use thiserror::Error;
@@ -0,0 +1,493 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_linux_agent/agent/src/hash_policy.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Binary hash allowlist/blocklist - signed policy file verified against pinned keys, SHA-256 of each executed binary checked at exec time, optional kill of blocklisted processes
+
+/*
+ * Binary Hash Policy
+ *
+ * Off unless AGENT_HASH_POLICY_PATH is set. The file holds a policy document signed (Ed25519)
+ * by a key pinned on this host (AGENT_HASH_POLICY_SIGNERS):
+ *
+ *   {
+ *     "document":   "{\"name\":\"fleet\",\"version\":7,\"block\":[...],\"allow\":[...],...}",
+ *     "signature":  "<base64 signature over document>",
+ *     "signer_key": "<hex public key>"
+ *   }
+ *
+ * The document lists SHA-256 values to block and to allow, each with an optional note. A policy
+ * that does not verify is refused: at startup the agent does not start, on reload the current
+ * policy stays in force. A reloaded policy must carry a higher version (no rollback).
+ *
+ * Every exec is hashed (the running image through /proc/<pid>/exe when it is still the
+ * executed file, otherwise the path; hashes are cached per inode, size and mtime):
+ *   - blocklisted: the event carries the verdict at critical severity and is delivered at
+ *     critical priority. With AGENT_HASH_POLICY_ENFORCE the process is killed (SIGKILL through
+ *     a pidfd, after checking the pid still runs the same binary).
+ *   - allowlisted: the event is delivered at telemetry priority, so it is shed before any other
+ *     exec under pressure.
+ *   - unlisted: the event carries the hash only.
+ */
+
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use crypto::digest::Sha256;
+use crypto::signature::{verify_ed25519, ED25519_PUBLIC_KEY_LEN};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use super::errors::AgentError;
+
+/// Most hashes one policy may list
+pub const MAX_POLICY_ENTRIES: usize = 100_000;
+/// Cached file hashes; the cache is cleared when full
+const HASH_CACHE_ENTRIES: usize = 4096;
+const MAX_NOTE_CHARS: usize = 256;
+
+/// Policy file: the document exactly as signed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedHashPolicy {
+    pub document: String,
+    /// Base64 Ed25519 signature over `document`
+    pub signature: String,
+    /// Hex Ed25519 public key of the signer
+    pub signer_key: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HashPolicyEntry {
+    pub sha256: String,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+/// Signed policy document
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HashPolicyDocument {
+    pub name: String,
+    /// Monotonic: a reloaded policy must exceed the one in force
+    pub version: i64,
+    #[serde(default)]
+    pub block: Vec<HashPolicyEntry>,
+    #[serde(default)]
+    pub allow: Vec<HashPolicyEntry>,
+    pub reason: String,
+    pub created_by: String,
+}
+
+/// Policy in force
+#[derive(Debug, Clone, Default)]
+pub struct HashPolicy {
+    pub name: String,
+    pub version: i64,
+    block: HashMap<String, Option<String>>,
+    allow: HashMap<String, Option<String>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashVerdict {
+    Blocked,
+    Allowed,
+    Unlisted,
+}
+
+impl HashVerdict {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HashVerdict::Blocked => "blocked",
+            HashVerdict::Allowed => "allowed",
+            HashVerdict::Unlisted => "unlisted",
+        }
+    }
+}
+
+/// Verdict on one exec, carried in the process event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HashPolicyMatch {
+    /// "blocked" or "allowed"
+    pub verdict: String,
+    /// "critical" for blocked binaries, "info" for allowed ones
+    pub severity: String,
+    pub policy_name: String,
+    pub policy_version: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// "killed" or "kill_failed" when enforcement is enabled; absent otherwise
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enforcement: Option<String>,
+}
+
+/// Hash and verdict of an executed binary
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecHash {
+    pub sha256: String,
+    pub verdict: HashVerdict,
+    /// Present for blocked and allowed binaries
+    pub policy_match: Option<HashPolicyMatch>,
+}
+
+#[derive(Debug, Clone)]
+pub struct HashPolicyConfig {
+    pub path: PathBuf,
+    pub signers: Vec<[u8; ED25519_PUBLIC_KEY_LEN]>,
+    /// Kill blocklisted processes
+    pub enforce: bool,
+    /// Larger binaries are not hashed
+    pub max_file_bytes: u64,
+}
+
+/// Comma-separated hex Ed25519 public keys (AGENT_HASH_POLICY_SIGNERS).
+pub fn parse_signers(list: &str) -> Result<Vec<[u8; ED25519_PUBLIC_KEY_LEN]>, AgentError> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|e| !e.is_empty())
+        .map(|entry| {
+            hex::decode(entry)
+                .ok()
+                .and_then(|b| b.try_into().ok())
+                .ok_or_else(|| AgentError::ConfigurationError(format!("AGENT_HASH_POLICY_SIGNERS: '{}' is not a hex Ed25519 public key", entry)))
+        })
+        .collect()
+}
+
+fn entries(list: Vec<HashPolicyEntry>, kind: &str) -> Result<HashMap<String, Option<String>>, String> {
+    let mut out = HashMap::with_capacity(list.len());
+    for entry in list {
+        let sha256 = entry.sha256.trim().to_ascii_lowercase();
+        if sha256.len() != 64 || !sha256.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(format!("{} entry '{}' is not a hex SHA-256", kind, entry.sha256));
+        }
+        out.insert(sha256, entry.note.map(|n| n.chars().take(MAX_NOTE_CHARS).collect()));
+    }
+    Ok(out)
+}
+
+/// The policy in `signed`, if a pinned key signed it and it is newer than `current`.
+pub fn verify_policy(
+    signers: &[[u8; ED25519_PUBLIC_KEY_LEN]],
+    signed: &SignedHashPolicy,
+    current: Option<i64>,
+) -> Result<HashPolicy, AgentError> {
+    let refuse = |reason: String| AgentError::ConfigurationError(format!("hash policy refused: {}", reason));
+    let signer_key = hex::decode(signed.signer_key.trim()).map_err(|_| refuse("signer key is not hex".to_string()))?;
+    if !signers.iter().any(|k| k.as_slice() == signer_key.as_slice()) {
+        return Err(refuse(format!("signer {} is not pinned on this host", signed.signer_key)));
+    }
+    let signature = STANDARD.decode(signed.signature.trim()).map_err(|_| refuse("signature is not base64".to_string()))?;
+    verify_ed25519(&signer_key, signed.document.as_bytes(), &signature)
+        .map_err(|_| refuse("signature does not verify".to_string()))?;
+    let doc: HashPolicyDocument =
+        serde_json::from_str(&signed.document).map_err(|e| refuse(format!("invalid document: {}", e)))?;
+    if doc.version < 1 {
+        return Err(refuse(format!("version {} is not positive", doc.version)));
+    }
+    if current.is_some_and(|v| doc.version <= v) {
+        return Err(refuse(format!("version {} is not newer than {}", doc.version, current.unwrap_or_default())));
+    }
+    if doc.block.len() + doc.allow.len() > MAX_POLICY_ENTRIES {
+        return Err(refuse(format!("more than {} entries", MAX_POLICY_ENTRIES)));
+    }
+    let block = entries(doc.block, "block").map_err(refuse)?;
+    let allow = entries(doc.allow, "allow").map_err(refuse)?;
+    if let Some(both) = block.keys().find(|h| allow.contains_key(*h)) {
+        return Err(refuse(format!("{} is both blocked and allowed", both)));
+    }
+    Ok(HashPolicy { name: doc.name, version: doc.version, block, allow })
+}
+
+impl HashPolicy {
+    pub fn verdict(&self, sha256: &str) -> (HashVerdict, Option<&String>) {
+        if let Some(note) = self.block.get(sha256) {
+            return (HashVerdict::Blocked, note.as_ref());
+        }
+        if let Some(note) = self.allow.get(sha256) {
+            return (HashVerdict::Allowed, note.as_ref());
+        }
+        (HashVerdict::Unlisted, None)
+    }
+}
+
+/// Cache key: the same inode, size and mtime is the same content
+type FileKey = (u64, u64, u64, Option<SystemTime>);
+
+fn file_key(meta: &std::fs::Metadata) -> FileKey {
+    (meta.dev(), meta.ino(), meta.len(), meta.modified().ok())
+}
+
+fn hash_file(path: &Path, max_file_bytes: u64) -> std::io::Result<(FileKey, String)> {
+    let mut file = std::fs::File::open(path)?;
+    let meta = file.metadata()?;
+    if !meta.is_file() {
+        return Err(std::io::Error::other("not a regular file"));
+    }
+    if meta.len() > max_file_bytes {
+        return Err(std::io::Error::other(format!("larger than {} bytes", max_file_bytes)));
+    }
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok((file_key(&meta), hex::encode(hasher.finalize())))
+}
+
+/// The image `pid` runs, if it is still `executable`.
+fn proc_exe(pid: u32, executable: &str) -> Option<PathBuf> {
+    let exe = PathBuf::from(format!("/proc/{}/exe", pid));
+    let target = std::fs::read_link(&exe).ok()?;
+    let target = target.to_string_lossy();
+    (target.trim_end_matches(" (deleted)") == executable).then_some(exe)
+}
+
+/// SIGKILL `pid` through a pidfd, after checking it still runs `executable` (a reused pid is
+/// never signalled).
+fn kill_exec(pid: u32, executable: &str) -> Result<(), String> {
+    if pid <= 1 || pid == std::process::id() {
+        return Err(format!("refusing to kill pid {}", pid));
+    }
+    // SAFETY: pidfd_open takes a pid and flags and returns a new descriptor or -1
+    let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid as libc::pid_t, 0) };
+    if fd < 0 {
+        return Err(format!("pidfd_open: {}", std::io::Error::last_os_error()));
+    }
+    let fd = fd as libc::c_int;
+    let result = if proc_exe(pid, executable).is_none() {
+        Err("process no longer runs the blocked binary".to_string())
+    } else {
+        // SAFETY: fd is the pidfd opened above; a null siginfo sends a plain kill
+        let rc = unsafe { libc::syscall(libc::SYS_pidfd_send_signal, fd, libc::SIGKILL, std::ptr::null::<libc::siginfo_t>(), 0) };
+        if rc < 0 {
+            Err(format!("pidfd_send_signal: {}", std::io::Error::last_os_error()))
+        } else {
+            Ok(())
+        }
+    };
+    // SAFETY: fd is owned here and closed once
+    unsafe { libc::close(fd) };
+    result
+}
+
+/// Policy in force and the exec-time evaluation against it
+pub struct HashPolicyEvaluator {
+    config: HashPolicyConfig,
+    policy: RwLock<HashPolicy>,
+    /// Modification time of the policy file last loaded
+    loaded_mtime: Mutex<Option<SystemTime>>,
+    cache: Mutex<HashMap<FileKey, String>>,
+    reported_errors: Mutex<HashSet<String>>,
+}
+
+impl HashPolicyEvaluator {
+    /// Load and verify the policy file (FAIL-CLOSED: an invalid policy stops the agent).
+    pub fn new(config: HashPolicyConfig) -> Result<Self, AgentError> {
+        let (signed, mtime) = read_policy_file(&config.path)?;
+        let policy = verify_policy(&config.signers, &signed, None)?;
+        info!("Hash policy loaded | name={} | version={} | block={} | allow={} | enforce={}",
+            policy.name, policy.version, policy.block.len(), policy.allow.len(), config.enforce);
+        Ok(Self {
+            config,
+            policy: RwLock::new(policy),
+            loaded_mtime: Mutex::new(mtime),
+            cache: Mutex::new(HashMap::new()),
+            reported_errors: Mutex::new(HashSet::new()),
+        })
+    }
+
+    pub fn version(&self) -> i64 {
+        self.policy.read().version
+    }
+
+    /// Load the policy file again if it changed. A policy that does not verify, or is not newer
+    /// than the one in force, is refused and the current one stays.
+    pub fn reload(&self) -> bool {
+        let mtime = std::fs::metadata(&self.config.path).and_then(|m| m.modified()).ok();
+        if mtime.is_some() && mtime == *self.loaded_mtime.lock() {
+            return false;
+        }
+        let loaded = read_policy_file(&self.config.path)
+            .and_then(|(signed, mtime)| Ok((verify_policy(&self.config.signers, &signed, Some(self.version()))?, mtime)));
+        *self.loaded_mtime.lock() = mtime;
+        match loaded {
+            Ok((policy, _)) => {
+                info!("Hash policy reloaded | name={} | version={} | block={} | allow={}",
+                    policy.name, policy.version, policy.block.len(), policy.allow.len());
+                *self.policy.write() = policy;
+                true
+            }
+            Err(e) => {
+                error!("{}; version {} stays in force", e, self.version());
+                false
+            }
+        }
+    }
+
+    fn sha256(&self, pid: u32, executable: &str) -> Result<String, String> {
+        let path = proc_exe(pid, executable).unwrap_or_else(|| PathBuf::from(executable));
+        if let Ok(meta) = std::fs::metadata(&path) {
+            if let Some(hash) = self.cache.lock().get(&file_key(&meta)) {
+                return Ok(hash.clone());
+            }
+        }
+        let (key, hash) = hash_file(&path, self.config.max_file_bytes).map_err(|e| e.to_string())?;
+        let mut cache = self.cache.lock();
+        if cache.len() >= HASH_CACHE_ENTRIES {
+            cache.clear();
+        }
+        cache.insert(key, hash.clone());
+        Ok(hash)
+    }
+
+    /// Hash the binary `pid` executed and judge it; blocked binaries are killed when enforcing.
+    /// `None` when the binary could not be hashed (logged once per executable).
+    pub fn evaluate_exec(&self, pid: u32, executable: &str) -> Option<ExecHash> {
+        let sha256 = match self.sha256(pid, executable) {
+            Ok(sha256) => sha256,
+            Err(e) => {
+                if self.reported_errors.lock().insert(executable.to_string()) {
+                    warn!("Hash policy: cannot hash {}: {}", executable, e);
+                }
+                return None;
+            }
+        };
+        let policy = self.policy.read();
+        let (verdict, note) = policy.verdict(&sha256);
+        let policy_match = match verdict {
+            HashVerdict::Unlisted => None,
+            HashVerdict::Blocked | HashVerdict::Allowed => Some(HashPolicyMatch {
+                verdict: verdict.as_str().to_string(),
+                severity: if verdict == HashVerdict::Blocked { "critical" } else { "info" }.to_string(),
+                policy_name: policy.name.clone(),
+                policy_version: policy.version,
+                note: note.cloned(),
+                enforcement: None,
+            }),
+        };
+        drop(policy);
+        let mut exec = ExecHash { sha256, verdict, policy_match };
+        if verdict == HashVerdict::Blocked {
+            let enforcement = self.config.enforce.then(|| match kill_exec(pid, executable) {
+                Ok(()) => "killed",
+                Err(e) => {
+                    error!("Hash policy: failed to kill pid {} ({}): {}", pid, executable, e);
+                    "kill_failed"
+                }
+            });
+            warn!("Blocklisted binary executed | pid={} | executable={} | sha256={} | enforcement={}",
+                pid, executable, exec.sha256, enforcement.unwrap_or("off"));
+            if let Some(m) = exec.policy_match.as_mut() {
+                m.enforcement = enforcement.map(str::to_string);
+            }
+        }
+        Some(exec)
+    }
+}
+
+fn read_policy_file(path: &Path) -> Result<(SignedHashPolicy, Option<SystemTime>), AgentError> {
+    let failed = |e: String| AgentError::ConfigurationError(format!("hash policy {}: {}", path.display(), e));
+    let mut file = std::fs::File::open(path).map_err(|e| failed(e.to_string()))?;
+    let mtime = file.metadata().and_then(|m| m.modified()).ok();
+    let mut text = String::new();
+    file.read_to_string(&mut text).map_err(|e| failed(e.to_string()))?;
+    let signed = serde_json::from_str(&text).map_err(|e| failed(format!("invalid policy file: {}", e)))?;
+    Ok((signed, mtime))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crypto::signature::Ed25519KeyPair;
+
+    fn sha256_hex(data: &[u8]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(data);
+        hex::encode(hasher.finalize())
+    }
+
+    fn signed(key: &Ed25519KeyPair, version: i64, block: &[&str], allow: &[&str]) -> SignedHashPolicy {
+        let list = |hashes: &[&str]| hashes.iter().map(|h| serde_json::json!({"sha256": h, "note": "test"})).collect::<Vec<_>>();
+        let document = serde_json::json!({
+            "name": "fleet",
+            "version": version,
+            "block": list(block),
+            "allow": list(allow),
+            "reason": "CHG-3101",
+            "created_by": "secops",
+        })
+        .to_string();
+        SignedHashPolicy {
+            signature: STANDARD.encode(key.sign(document.as_bytes())),
+            signer_key: hex::encode(key.public_key()),
+            document,
+        }
+    }
+
+    #[test]
+    fn test_policy_verifies_against_pinned_keys_only() {
+        let key = Ed25519KeyPair::generate().unwrap();
+        let stranger = Ed25519KeyPair::generate().unwrap();
+        let signers = parse_signers(&hex::encode(key.public_key())).unwrap();
+        let blocked = "ab".repeat(32);
+
+        let policy = verify_policy(&signers, &signed(&key, 3, &[&blocked], &[]), None).unwrap();
+        assert_eq!(policy.verdict(&blocked).0, HashVerdict::Blocked);
+        assert!(verify_policy(&signers, &signed(&stranger, 3, &[&blocked], &[]), None).is_err());
+        // No rollback, no hash on both lists, no malformed hash
+        assert!(verify_policy(&signers, &signed(&key, 3, &[&blocked], &[]), Some(3)).is_err());
+        assert!(verify_policy(&signers, &signed(&key, 4, &[&blocked], &[&blocked]), None).is_err());
+        assert!(verify_policy(&signers, &signed(&key, 4, &["abc"], &[]), None).is_err());
+
+        let mut tampered = signed(&key, 5, &[], &[]);
+        tampered.document = tampered.document.replace("\"version\":5", "\"version\":6");
+        assert!(verify_policy(&signers, &tampered, None).is_err());
+    }
+
+    #[test]
+    fn test_exec_verdicts_and_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = Ed25519KeyPair::generate().unwrap();
+        let bad = dir.path().join("dropper");
+        let good = dir.path().join("backup");
+        std::fs::write(&bad, b"encrypt everything").unwrap();
+        std::fs::write(&good, b"copy everything").unwrap();
+        let (bad_hash, good_hash) = (sha256_hex(b"encrypt everything"), sha256_hex(b"copy everything"));
+
+        let path = dir.path().join("policy.json");
+        std::fs::write(&path, serde_json::to_vec(&signed(&key, 1, &[&bad_hash], &[&good_hash])).unwrap()).unwrap();
+        let evaluator = HashPolicyEvaluator::new(HashPolicyConfig {
+            path: path.clone(),
+            signers: vec![key.public_key().try_into().unwrap()],
+            enforce: false,
+            max_file_bytes: 1024,
+        })
+        .unwrap();
+
+        let pid = std::process::id();
+        let exec = evaluator.evaluate_exec(pid, bad.to_str().unwrap()).unwrap();
+        assert_eq!(exec.verdict, HashVerdict::Blocked);
+        let m = exec.policy_match.unwrap();
+        assert_eq!((m.severity.as_str(), m.policy_version, m.enforcement), ("critical", 1, None));
+        assert_eq!(evaluator.evaluate_exec(pid, good.to_str().unwrap()).unwrap().verdict, HashVerdict::Allowed);
+        assert!(evaluator.evaluate_exec(pid, "/nonexistent/binary").is_none());
+
+        // A rolled-back policy is refused; a newer one takes over
+        std::fs::write(&path, serde_json::to_vec(&signed(&key, 1, &[], &[])).unwrap()).unwrap();
+        *evaluator.loaded_mtime.lock() = None;
+        assert!(!evaluator.reload());
+        assert_eq!(evaluator.evaluate_exec(pid, bad.to_str().unwrap()).unwrap().verdict, HashVerdict::Blocked);
+        std::fs::write(&path, serde_json::to_vec(&signed(&key, 2, &[], &[])).unwrap()).unwrap();
+        *evaluator.loaded_mtime.lock() = None;
+        assert!(evaluator.reload());
+        let exec = evaluator.evaluate_exec(pid, bad.to_str().unwrap()).unwrap();
+        assert_eq!((exec.verdict, exec.sha256), (HashVerdict::Unlisted, bad_hash));
+    }
+}
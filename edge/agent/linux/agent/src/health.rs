@@ -1,11 +1,17 @@
 // Path and File Name : /home/ransomeye/rebuild/ransomeye_linux_agent/agent/src/health.rs
 // Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
-// Details of functionality of this file: Health monitoring for Linux Agent
+// Details of functionality of this file: Health monitoring for Linux Agent, reported to Core in the shared health model
 
 use std::sync::atomic::{AtomicU64, AtomicBool, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH};
+use ::health::{HealthReport, HealthStatus};
+use parking_lot::Mutex;
 use tracing::warn;
 
+use super::delivery::{CircuitState, DeliveryStats};
+use super::disk_budget::DiskUsage;
+use super::kernel_caps::{HookSource, KernelCapabilities};
+use super::yara_scan::YaraScanStatus;
 use super::errors::AgentError;
 
 /// Health monitor
@@ -19,6 +25,10 @@ pub struct HealthMonitor {
     errors_count: AtomicU64,
     healthy: AtomicBool,
     max_idle_time: u64, // seconds
+    delivery: Mutex<Option<DeliveryStats>>,
+    disk: Mutex<Option<DiskUsage>>,
+    kernel_capabilities: Mutex<Option<KernelCapabilities>>,
+    yara: Mutex<Option<YaraScanStatus>>,
 }
 
 impl HealthMonitor {
@@ -36,6 +46,10 @@ impl HealthMonitor {
             errors_count: AtomicU64::new(0),
             healthy: AtomicBool::new(true),
             max_idle_time,
+            delivery: Mutex::new(None),
+            disk: Mutex::new(None),
+            kernel_capabilities: Mutex::new(None),
+            yara: Mutex::new(None),
         }
     }
     
@@ -55,6 +69,36 @@ impl HealthMonitor {
         self.errors_count.fetch_add(1, Ordering::Relaxed);
     }
     
+    /// Record latest delivery state (circuit breaker, spool) for health reporting.
+    /// An open circuit does not make the agent unhealthy: events are spooled, not lost.
+    pub fn record_delivery(&self, stats: DeliveryStats) {
+        *self.delivery.lock() = Some(stats);
+    }
+    
+    /// Record disk budget usage. An exceeded budget marks the agent unhealthy in reported status
+    /// (events are being shed locally) without stopping the main loop.
+    pub fn record_disk(&self, usage: DiskUsage) {
+        let mut disk = self.disk.lock();
+        let was_exceeded = disk.map_or(false, |d| d.exceeded);
+        if usage.exceeded && !was_exceeded {
+            warn!("Disk budget exceeded: spool={} bytes, logs={} bytes, budget={} bytes - reporting unhealthy",
+                usage.spool_bytes, usage.log_bytes, usage.budget_bytes);
+        }
+        *disk = Some(usage);
+    }
+    
+    /// Record the syscall hook capabilities probed on this kernel. Degraded hooks are reported,
+    /// not treated as unhealthy: the agent runs with whatever the kernel supports.
+    pub fn record_kernel_capabilities(&self, capabilities: KernelCapabilities) {
+        *self.kernel_capabilities.lock() = Some(capabilities);
+    }
+    
+    /// Record the installed YARA rule pack and the last scan. Matches and scan errors are
+    /// reported, not treated as unhealthy.
+    pub fn record_yara(&self, status: YaraScanStatus) {
+        *self.yara.lock() = Some(status);
+    }
+    
     /// Check health status
     pub fn check_health(&self) -> Result<bool, AgentError> {
         let now = SystemTime::now()
@@ -99,17 +143,83 @@ impl HealthMonitor {
             uptime: now.saturating_sub(self.start_time),
             events_processed: self.events_processed.load(Ordering::Relaxed),
             errors_count: self.errors_count.load(Ordering::Relaxed),
-            healthy: self.healthy.load(Ordering::Relaxed),
+            healthy: self.is_healthy(),
             last_event_time: self.last_event_time.load(Ordering::Relaxed),
+            delivery: self.delivery.lock().clone(),
+            disk: *self.disk.lock(),
+            kernel_capabilities: self.kernel_capabilities.lock().clone(),
+            yara: self.yara.lock().clone(),
         }
     }
     
-    /// Check if healthy
+    /// Check if healthy (liveness and disk budget)
     pub fn is_healthy(&self) -> bool {
-        self.healthy.load(Ordering::Acquire)
+        self.healthy.load(Ordering::Acquire) && !self.disk.lock().map_or(false, |d| d.exceeded)
+    }
+    
+    /// Health report sent to Core with agent stats. Liveness and disk budget decide between
+    /// healthy and unhealthy as `is_healthy` does; delivery, kernel hooks and YARA only degrade.
+    pub fn report(&self, instance_id: &str) -> HealthReport {
+        let stats = self.stats();
+        let mut report = HealthReport::new(COMPONENT, instance_id);
+        
+        if self.healthy.load(Ordering::Acquire) {
+            report.push_subsystem("liveness", HealthStatus::Healthy, None);
+        } else {
+            report.push_subsystem("liveness", HealthStatus::Unhealthy,
+                Some("idle too long or error rate over 10%".to_string()));
+        }
+        
+        if let Some(disk) = &stats.disk {
+            let (status, detail) = if disk.exceeded {
+                (HealthStatus::Unhealthy, Some(format!("budget exceeded: spool={} bytes, logs={} bytes, budget={} bytes",
+                    disk.spool_bytes, disk.log_bytes, disk.budget_bytes)))
+            } else {
+                (HealthStatus::Healthy, None)
+            };
+            report.push_subsystem("disk", status, detail);
+            report.set_metric("disk_used_bytes", (disk.spool_bytes + disk.log_bytes) as f64);
+        }
+        
+        if let Some(delivery) = &stats.delivery {
+            match delivery.circuit_state {
+                CircuitState::Closed => report.push_subsystem("delivery", HealthStatus::Healthy, None),
+                state => report.push_subsystem("delivery", HealthStatus::Degraded,
+                    Some(format!("circuit {}, {} event(s) spooled", state.as_str(), delivery.spool_depth))),
+            }
+            report.set_metric("spool_depth", delivery.spool_depth as f64);
+            report.set_metric("events_delivered", delivery.delivered as f64);
+        }
+        
+        if let Some(caps) = &stats.kernel_capabilities {
+            if caps.degraded() {
+                report.push_subsystem("kernel_hooks", HealthStatus::Degraded,
+                    Some(format!("{} of {} hook(s) not on eBPF", caps.hooks.len() - caps.count(HookSource::Ebpf), caps.hooks.len())));
+            } else {
+                report.push_subsystem("kernel_hooks", HealthStatus::Healthy, None);
+            }
+        }
+        
+        if let Some(scan) = stats.yara.as_ref().and_then(|y| y.last_scan.as_ref()) {
+            if let Some(failure) = &scan.failure {
+                report.push_subsystem("yara", HealthStatus::Degraded, Some(format!("last scan failed: {}", failure)));
+            } else if scan.errors > 0 {
+                report.push_subsystem("yara", HealthStatus::Degraded, Some(format!("{} error(s) in last scan", scan.errors)));
+            } else {
+                report.push_subsystem("yara", HealthStatus::Healthy, None);
+            }
+        }
+        
+        report.set_metric("uptime_secs", stats.uptime as f64);
+        report.set_metric("events_processed", stats.events_processed as f64);
+        report.set_metric("errors", stats.errors_count as f64);
+        report
     }
 }
 
+/// Component name in health reports (as in the agent's envelopes)
+pub const COMPONENT: &str = "linux_agent";
+
 #[derive(Debug, Clone)]
 pub struct HealthStats {
     pub uptime: u64,
@@ -117,5 +227,63 @@ pub struct HealthStats {
     pub errors_count: u64,
     pub healthy: bool,
     pub last_event_time: u64,
+    pub delivery: Option<DeliveryStats>,
+    pub disk: Option<DiskUsage>,
+    pub kernel_capabilities: Option<KernelCapabilities>,
+    pub yara: Option<YaraScanStatus>,
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel_caps::{Hook, HookStatus};
+
+    fn delivery(circuit_state: CircuitState, spool_depth: usize) -> DeliveryStats {
+        DeliveryStats {
+            circuit_state,
+            consecutive_failures: 0,
+            delivered: 40,
+            retries: 0,
+            retry_budget_exhausted: 0,
+            spooled: spool_depth as u64,
+            rejected: 0,
+            rejected_by_category: Default::default(),
+            spool_depth,
+            spool_bytes: 0,
+            spool_dropped: 0,
+            bandwidth_wait_ms: 0,
+            backlog_deferred: 0,
+        }
+    }
+
+    #[test]
+    fn test_report_degrades_on_delivery_and_hooks_and_fails_on_disk() {
+        let monitor = HealthMonitor::new(300);
+        monitor.record_delivery(delivery(CircuitState::Closed, 0));
+        monitor.record_disk(DiskUsage { spool_bytes: 10, log_bytes: 20, budget_bytes: 100, exceeded: false });
+        let report = monitor.report("agent-1");
+        assert_eq!(report.component, COMPONENT);
+        assert_eq!(report.status, HealthStatus::Healthy);
+        assert_eq!(report.metrics["disk_used_bytes"], 30.0);
+        assert!(report.subsystem("kernel_hooks").is_none());
+
+        monitor.record_delivery(delivery(CircuitState::Open, 5));
+        monitor.record_kernel_capabilities(KernelCapabilities {
+            kernel_release: "5.4.0".to_string(),
+            btf: false,
+            ringbuf: false,
+            bpf_lsm: false,
+            hooks: vec![HookStatus { hook: Hook::ProcessExec, source: HookSource::Auditd, attach_point: None, reason: None }],
+        });
+        let report = monitor.report("agent-1");
+        assert_eq!(report.status, HealthStatus::Degraded);
+        assert_eq!(report.subsystem("delivery").unwrap().detail.as_deref(), Some("circuit open, 5 event(s) spooled"));
+        assert_eq!(report.subsystem("kernel_hooks").unwrap().detail.as_deref(), Some("1 of 1 hook(s) not on eBPF"));
+
+        monitor.record_disk(DiskUsage { spool_bytes: 90, log_bytes: 20, budget_bytes: 100, exceeded: true });
+        let report = monitor.report("agent-1");
+        assert_eq!(report.status, HealthStatus::Unhealthy);
+        assert!(!monitor.is_healthy());
+        assert_eq!(report.status_details().unwrap().split("; ").next().unwrap().split(':').next(), Some("disk"));
+    }
+}
@@ -0,0 +1,306 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_linux_agent/agent/src/inventory.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Host inventory introspection - OS release, kernel, CPU and memory, installed security tooling and disk encryption status, reported to Core on startup and daily
+
+/*
+ * Host Inventory
+ *
+ * Facts are read from files below the host root (AGENT_HOST_ROOT, "/" outside containers):
+ *
+ *   os          etc/os-release (usr/lib/os-release as fallback)
+ *   kernel      proc/sys/kernel/osrelease and version
+ *   cpu/memory  proc/cpuinfo, proc/meminfo
+ *   tooling     known binaries of EDR/AV/HIDS/FIM agents, SELinux enforce and AppArmor state
+ *   encryption  dm-crypt mappings in sys/block/dm-N/dm (directly or below LVM), and whether the
+ *               root filesystem (proc/1/mounts) sits on one
+ *
+ * Everything is best effort: a missing file leaves its field empty instead of failing the report.
+ * Only dm-crypt is detected; self-encrypting drives report as none.
+ */
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Installed security tooling found on the host
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SecurityTool {
+    pub name: String,
+    /// edr, antivirus, hids, fim, audit, runtime, mac
+    pub kind: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiskEncryption {
+    /// Root filesystem is on dm-crypt
+    Encrypted,
+    /// dm-crypt volumes exist, but the root filesystem is not on one
+    Partial,
+    None,
+    /// Root device could not be resolved (e.g. overlay root) and no dm-crypt volume was found
+    Unknown,
+}
+
+/// Inventory report (serialized as data.host_inventory)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HostInventory {
+    pub hostname: Option<String>,
+    pub os_name: Option<String>,
+    pub os_version: Option<String>,
+    pub kernel_release: String,
+    pub kernel_version: Option<String>,
+    pub arch: String,
+    pub cpu_model: Option<String>,
+    pub cpu_cores: u32,
+    pub memory_total_bytes: u64,
+    pub security_tools: Vec<SecurityTool>,
+    pub disk_encryption: DiskEncryption,
+    /// dm-crypt mapping names
+    pub encrypted_volumes: Vec<String>,
+}
+
+/// (name, kind, paths below the host root - any one present means installed)
+const KNOWN_TOOLS: &[(&str, &str, &[&str])] = &[
+    ("crowdstrike_falcon", "edr", &["opt/CrowdStrike/falcond"]),
+    ("sentinelone", "edr", &["opt/sentinelone/bin/sentinelone-agent"]),
+    ("microsoft_defender", "edr", &["opt/microsoft/mdatp/sbin/wdavdaemon"]),
+    ("osquery", "edr", &["usr/bin/osqueryd", "opt/osquery/bin/osqueryd"]),
+    ("clamav", "antivirus", &["usr/sbin/clamd", "usr/bin/clamscan"]),
+    ("wazuh", "hids", &["var/ossec/bin/wazuh-agentd"]),
+    ("ossec", "hids", &["var/ossec/bin/ossec-agentd"]),
+    ("aide", "fim", &["usr/bin/aide", "usr/sbin/aide"]),
+    ("auditd", "audit", &["sbin/auditd", "usr/sbin/auditd"]),
+    ("falco", "runtime", &["usr/bin/falco"]),
+];
+
+/// Reads host facts under a root directory
+pub struct InventoryCollector {
+    root: PathBuf,
+}
+
+impl InventoryCollector {
+    pub fn new(host_root: impl AsRef<Path>) -> Self {
+        Self { root: host_root.as_ref().to_path_buf() }
+    }
+
+    fn read(&self, relative: &str) -> Option<String> {
+        fs::read_to_string(self.root.join(relative)).ok()
+    }
+
+    pub fn collect(&self) -> HostInventory {
+        let os_release = self.read("etc/os-release").or_else(|| self.read("usr/lib/os-release")).unwrap_or_default();
+        let (os_name, os_version) = parse_os_release(&os_release);
+        let (cpu_model, cpu_cores) = parse_cpuinfo(&self.read("proc/cpuinfo").unwrap_or_default());
+        let (disk_encryption, encrypted_volumes) = self.disk_encryption();
+        HostInventory {
+            hostname: self.read("proc/sys/kernel/hostname").map(|s| s.trim().to_string()).filter(|s| !s.is_empty()),
+            os_name,
+            os_version,
+            kernel_release: self.read("proc/sys/kernel/osrelease").map(|s| s.trim().to_string()).unwrap_or_default(),
+            kernel_version: self.read("proc/sys/kernel/version").map(|s| s.trim().to_string()),
+            arch: std::env::consts::ARCH.to_string(),
+            cpu_model,
+            cpu_cores,
+            memory_total_bytes: parse_meminfo_total(&self.read("proc/meminfo").unwrap_or_default()),
+            security_tools: self.security_tools(),
+            disk_encryption,
+            encrypted_volumes,
+        }
+    }
+
+    fn security_tools(&self) -> Vec<SecurityTool> {
+        let tool = |name: &str, kind: &str| SecurityTool { name: name.to_string(), kind: kind.to_string() };
+        let mut tools: Vec<SecurityTool> = KNOWN_TOOLS
+            .iter()
+            .filter(|(_, _, paths)| paths.iter().any(|p| self.root.join(p).exists()))
+            .map(|(name, kind, _)| tool(name, kind))
+            .collect();
+        if self.read("sys/fs/selinux/enforce").is_some_and(|s| s.trim() == "1") {
+            tools.push(tool("selinux", "mac"));
+        }
+        if self.read("sys/module/apparmor/parameters/enabled").is_some_and(|s| s.trim() == "Y") {
+            tools.push(tool("apparmor", "mac"));
+        }
+        tools
+    }
+
+    /// dm device names ("dm-0") that are dm-crypt mappings, with their mapping names
+    fn crypt_devices(&self) -> Vec<(String, String)> {
+        let Ok(entries) = fs::read_dir(self.root.join("sys/block")) else {
+            return Vec::new();
+        };
+        let mut devices: Vec<(String, String)> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .filter(|dev| dev.starts_with("dm-") && self.is_crypt(dev))
+            .map(|dev| {
+                let name = self.read(&format!("sys/block/{}/dm/name", dev)).map(|s| s.trim().to_string()).unwrap_or_else(|| dev.clone());
+                (dev, name)
+            })
+            .collect();
+        devices.sort();
+        devices
+    }
+
+    fn is_crypt(&self, dev: &str) -> bool {
+        self.read(&format!("sys/block/{}/dm/uuid", dev)).is_some_and(|u| u.starts_with("CRYPT-"))
+    }
+
+    /// dm device of a mount source (/dev/mapper/<name> or /dev/dm-N)
+    fn dm_device(&self, source: &str) -> Option<String> {
+        if let Some(dev) = source.strip_prefix("/dev/").filter(|d| d.starts_with("dm-")) {
+            return Some(dev.to_string());
+        }
+        let name = source.strip_prefix("/dev/mapper/")?;
+        fs::read_dir(self.root.join("sys/block")).ok()?
+            .filter_map(|e| e.ok())
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .find(|dev| dev.starts_with("dm-") && self.read(&format!("sys/block/{}/dm/name", dev)).is_some_and(|n| n.trim() == name))
+    }
+
+    /// The device, or anything it is stacked on (LVM on LUKS), is dm-crypt
+    fn on_crypt(&self, dev: &str, depth: usize) -> bool {
+        if self.is_crypt(dev) {
+            return true;
+        }
+        if depth >= 8 {
+            return false;
+        }
+        fs::read_dir(self.root.join(format!("sys/block/{}/slaves", dev)))
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok())
+                    .map(|e| e.file_name().to_string_lossy().to_string())
+                    .any(|slave| slave.starts_with("dm-") && self.on_crypt(&slave, depth + 1))
+            })
+            .unwrap_or(false)
+    }
+
+    fn disk_encryption(&self) -> (DiskEncryption, Vec<String>) {
+        let volumes: Vec<String> = self.crypt_devices().into_iter().map(|(_, name)| name).collect();
+        let mounts = self.read("proc/1/mounts").or_else(|| self.read("proc/mounts")).unwrap_or_default();
+        let root_on_crypt = root_mount_source(&mounts)
+            .and_then(|source| self.dm_device(source).map(|dev| self.on_crypt(&dev, 0)).or_else(|| source.starts_with("/dev/").then_some(false)));
+        (classify_encryption(root_on_crypt, !volumes.is_empty()), volumes)
+    }
+}
+
+/// NAME and VERSION_ID from os-release (PRETTY_NAME / VERSION as fallbacks)
+pub fn parse_os_release(contents: &str) -> (Option<String>, Option<String>) {
+    let value = |key: &str| {
+        contents.lines().find_map(|line| {
+            let v = line.strip_prefix(key)?.strip_prefix('=')?;
+            let v = v.trim().trim_matches('"').trim_matches('\'');
+            (!v.is_empty()).then(|| v.to_string())
+        })
+    };
+    (value("NAME").or_else(|| value("PRETTY_NAME")), value("VERSION_ID").or_else(|| value("VERSION")))
+}
+
+/// First "model name" and the number of logical processors
+pub fn parse_cpuinfo(contents: &str) -> (Option<String>, u32) {
+    let model = contents.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        (key.trim() == "model name").then(|| value.trim().to_string())
+    });
+    let cores = contents
+        .lines()
+        .filter(|line| line.split_once(':').is_some_and(|(key, _)| key.trim() == "processor"))
+        .count() as u32;
+    (model, cores)
+}
+
+/// MemTotal in bytes
+pub fn parse_meminfo_total(contents: &str) -> u64 {
+    contents
+        .lines()
+        .find_map(|line| line.strip_prefix("MemTotal:")?.trim().strip_suffix("kB")?.trim().parse::<u64>().ok())
+        .map_or(0, |kb| kb * 1024)
+}
+
+/// Source device of the "/" mount (the last one wins, as in the kernel's mount stack)
+pub fn root_mount_source(mounts: &str) -> Option<&str> {
+    mounts
+        .lines()
+        .rev()
+        .find_map(|line| {
+            let mut fields = line.split_whitespace();
+            let source = fields.next()?;
+            (fields.next()? == "/").then_some(source)
+        })
+}
+
+/// `root_on_crypt` is None when the root device could not be resolved
+pub fn classify_encryption(root_on_crypt: Option<bool>, any_crypt_volume: bool) -> DiskEncryption {
+    match (root_on_crypt, any_crypt_volume) {
+        (Some(true), _) => DiskEncryption::Encrypted,
+        (_, true) => DiskEncryption::Partial,
+        (Some(false), false) => DiskEncryption::None,
+        (None, false) => DiskEncryption::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write(root: &Path, relative: &str, contents: &str) {
+        let path = root.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    fn dm(root: &Path, dev: &str, name: &str, uuid: &str, slaves: &[&str]) {
+        write(root, &format!("sys/block/{}/dm/name", dev), &format!("{}\n", name));
+        write(root, &format!("sys/block/{}/dm/uuid", dev), &format!("{}\n", uuid));
+        fs::create_dir_all(root.join(format!("sys/block/{}/slaves", dev))).unwrap();
+        for slave in slaves {
+            fs::create_dir_all(root.join(format!("sys/block/{}/slaves/{}", dev, slave))).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_parse_host_facts() {
+        assert_eq!(
+            parse_os_release("NAME=\"Ubuntu\"\nVERSION=\"22.04.4 LTS (Jammy Jellyfish)\"\nVERSION_ID=\"22.04\"\n"),
+            (Some("Ubuntu".to_string()), Some("22.04".to_string()))
+        );
+        assert_eq!(
+            parse_cpuinfo("processor\t: 0\nmodel name\t: AMD EPYC 7B13\n\nprocessor\t: 1\nmodel name\t: AMD EPYC 7B13\n"),
+            (Some("AMD EPYC 7B13".to_string()), 2)
+        );
+        assert_eq!(parse_meminfo_total("MemTotal:       16318412 kB\nMemFree: 1 kB\n"), 16318412 * 1024);
+        assert_eq!(root_mount_source("sysfs /sys sysfs rw 0 0\n/dev/sda1 / ext4 rw 0 0\n/dev/mapper/vg-root / ext4 rw 0 0\n"), Some("/dev/mapper/vg-root"));
+    }
+
+    #[test]
+    fn test_root_on_lvm_over_luks_is_encrypted() {
+        let root = TempDir::new().unwrap();
+        dm(root.path(), "dm-0", "luks-1234", "CRYPT-LUKS2-1234-luks-1234", &["sda3"]);
+        dm(root.path(), "dm-1", "vg-root", "LVM-abcd", &["dm-0"]);
+        write(root.path(), "proc/1/mounts", "/dev/mapper/vg-root / ext4 rw 0 0\n");
+        write(root.path(), "sys/fs/selinux/enforce", "1");
+        write(root.path(), "usr/sbin/auditd", "");
+
+        let inventory = InventoryCollector::new(root.path()).collect();
+        assert_eq!(inventory.disk_encryption, DiskEncryption::Encrypted);
+        assert_eq!(inventory.encrypted_volumes, vec!["luks-1234".to_string()]);
+        let tools: Vec<_> = inventory.security_tools.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(tools, vec!["auditd", "selinux"]);
+    }
+
+    #[test]
+    fn test_encryption_status() {
+        let root = TempDir::new().unwrap();
+        dm(root.path(), "dm-0", "data", "CRYPT-LUKS2-5678-data", &["sdb1"]);
+        write(root.path(), "proc/1/mounts", "/dev/sda1 / ext4 rw 0 0\n");
+        assert_eq!(InventoryCollector::new(root.path()).collect().disk_encryption, DiskEncryption::Partial);
+
+        assert_eq!(classify_encryption(Some(false), false), DiskEncryption::None);
+        // Overlay root (container without host mounts): cannot tell
+        assert_eq!(classify_encryption(None, false), DiskEncryption::Unknown);
+    }
+}
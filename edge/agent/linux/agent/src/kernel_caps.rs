@@ -0,0 +1,363 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_linux_agent/agent/src/kernel_caps.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Kernel capability probing for eBPF CO-RE - BTF, ring buffer and BPF LSM support plus per-hook attach point availability, with auditd fallback per hook
+
+/*
+ * Kernel Capability Probing
+ *
+ * The eBPF programs are CO-RE (compile once, run everywhere): field offsets are relocated at load
+ * time against the running kernel's BTF (/sys/kernel/btf/vmlinux), so one object works across
+ * kernels that have BTF. Without BTF no eBPF hook is loaded.
+ *
+ * Each hook lists its attach points in order of preference (tracepoints are a stable ABI; kprobes
+ * depend on the symbol existing in /proc/kallsyms). The first attach point the kernel has is used.
+ * A hook with none, or on a kernel without BTF, falls back to auditd when auditd is enabled and
+ * the hook maps to audited syscalls; otherwise it is reported unavailable. Monitoring degrades per
+ * hook: one missing tracepoint never disables the others.
+ *
+ * The resulting report is logged at startup and sent to Core in every agent_stats envelope
+ * (data.agent_stats.kernel_capabilities).
+ */
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Monitored kernel activity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Hook {
+    ProcessExec,
+    ProcessFork,
+    ProcessExit,
+    Mmap,
+    FileOpen,
+    FileWrite,
+    FileRename,
+    FileUnlink,
+    NetConnect,
+    NetAccept,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttachPoint {
+    /// tracefs events/<category>/<name>
+    Tracepoint(&'static str, &'static str),
+    /// Kernel function in /proc/kallsyms
+    Kprobe(&'static str),
+}
+
+impl AttachPoint {
+    pub fn describe(&self) -> String {
+        match self {
+            AttachPoint::Tracepoint(category, name) => format!("tracepoint:{}/{}", category, name),
+            AttachPoint::Kprobe(symbol) => format!("kprobe:{}", symbol),
+        }
+    }
+}
+
+/// Where a hook's events come from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookSource {
+    Ebpf,
+    Auditd,
+    Unavailable,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HookStatus {
+    pub hook: Hook,
+    pub source: HookSource,
+    /// eBPF attach point in use ("tracepoint:sched/sched_process_exec")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attach_point: Option<String>,
+    /// Why eBPF is not used for this hook
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KernelCapabilities {
+    pub kernel_release: String,
+    /// Kernel BTF present (required for CO-RE)
+    pub btf: bool,
+    /// BPF ring buffer (5.8+); older kernels use per-CPU perf buffers
+    pub ringbuf: bool,
+    /// "bpf" in the active LSM list
+    pub bpf_lsm: bool,
+    pub hooks: Vec<HookStatus>,
+}
+
+impl KernelCapabilities {
+    pub fn count(&self, source: HookSource) -> usize {
+        self.hooks.iter().filter(|h| h.source == source).count()
+    }
+
+    /// Some hook is not served by eBPF.
+    pub fn degraded(&self) -> bool {
+        self.hooks.iter().any(|h| h.source != HookSource::Ebpf)
+    }
+
+    pub fn hook(&self, hook: Hook) -> Option<&HookStatus> {
+        self.hooks.iter().find(|h| h.hook == hook)
+    }
+}
+
+struct HookSpec {
+    hook: Hook,
+    attach_points: &'static [AttachPoint],
+    /// Syscalls auditd rules cover for this hook (empty: no auditd fallback)
+    audit_syscalls: &'static [&'static str],
+}
+
+const HOOKS: &[HookSpec] = &[
+    HookSpec {
+        hook: Hook::ProcessExec,
+        attach_points: &[AttachPoint::Tracepoint("sched", "sched_process_exec")],
+        audit_syscalls: &["execve", "execveat"],
+    },
+    HookSpec {
+        hook: Hook::ProcessFork,
+        attach_points: &[AttachPoint::Tracepoint("sched", "sched_process_fork")],
+        audit_syscalls: &["clone", "clone3", "fork", "vfork"],
+    },
+    HookSpec {
+        hook: Hook::ProcessExit,
+        attach_points: &[AttachPoint::Tracepoint("sched", "sched_process_exit")],
+        audit_syscalls: &[],
+    },
+    HookSpec {
+        hook: Hook::Mmap,
+        attach_points: &[AttachPoint::Tracepoint("syscalls", "sys_enter_mmap")],
+        audit_syscalls: &["mmap"],
+    },
+    HookSpec {
+        hook: Hook::FileOpen,
+        attach_points: &[AttachPoint::Tracepoint("syscalls", "sys_enter_openat"), AttachPoint::Kprobe("do_sys_openat2")],
+        audit_syscalls: &["open", "openat"],
+    },
+    HookSpec {
+        hook: Hook::FileWrite,
+        attach_points: &[AttachPoint::Kprobe("vfs_write")],
+        audit_syscalls: &[],
+    },
+    HookSpec {
+        hook: Hook::FileRename,
+        attach_points: &[AttachPoint::Tracepoint("syscalls", "sys_enter_renameat2"), AttachPoint::Kprobe("vfs_rename")],
+        audit_syscalls: &["rename", "renameat", "renameat2"],
+    },
+    HookSpec {
+        hook: Hook::FileUnlink,
+        attach_points: &[AttachPoint::Tracepoint("syscalls", "sys_enter_unlinkat"), AttachPoint::Kprobe("vfs_unlink")],
+        audit_syscalls: &["unlink", "unlinkat"],
+    },
+    HookSpec {
+        hook: Hook::NetConnect,
+        attach_points: &[AttachPoint::Tracepoint("sock", "inet_sock_set_state"), AttachPoint::Kprobe("tcp_connect")],
+        audit_syscalls: &["connect"],
+    },
+    HookSpec {
+        hook: Hook::NetAccept,
+        attach_points: &[AttachPoint::Kprobe("inet_csk_accept")],
+        audit_syscalls: &["accept", "accept4"],
+    },
+];
+
+/// Syscalls the auditd fallback must watch for the hooks it serves.
+pub fn audit_syscalls(capabilities: &KernelCapabilities) -> Vec<&'static str> {
+    HOOKS
+        .iter()
+        .filter(|spec| capabilities.hook(spec.hook).is_some_and(|h| h.source == HookSource::Auditd))
+        .flat_map(|spec| spec.audit_syscalls.iter().copied())
+        .collect()
+}
+
+/// Reads kernel interfaces under a root directory ("/" in production).
+pub struct KernelProber {
+    root: PathBuf,
+}
+
+impl Default for KernelProber {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KernelProber {
+    pub fn new() -> Self {
+        Self::with_root("/")
+    }
+
+    /// Probe a copy of /sys and /proc under `root` (tests, offline diagnosis).
+    pub fn with_root(root: impl AsRef<Path>) -> Self {
+        Self { root: root.as_ref().to_path_buf() }
+    }
+
+    fn path(&self, relative: &str) -> PathBuf {
+        self.root.join(relative)
+    }
+
+    pub fn kernel_release(&self) -> String {
+        fs::read_to_string(self.path("proc/sys/kernel/osrelease"))
+            .map(|r| r.trim().to_string())
+            .unwrap_or_else(|_| "unknown".to_string())
+    }
+
+    pub fn has_btf(&self) -> bool {
+        self.path("sys/kernel/btf/vmlinux").is_file()
+    }
+
+    fn has_tracepoint(&self, category: &str, name: &str) -> bool {
+        ["sys/kernel/tracing/events", "sys/kernel/debug/tracing/events"]
+            .iter()
+            .any(|base| self.path(base).join(category).join(name).is_dir())
+    }
+
+    /// Kernel function symbols (text symbols only: kprobes attach to code).
+    fn kernel_functions(&self) -> HashSet<String> {
+        let kallsyms = fs::read_to_string(self.path("proc/kallsyms")).unwrap_or_default();
+        kallsyms
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.split_whitespace();
+                let (_addr, kind, name) = (parts.next()?, parts.next()?, parts.next()?);
+                matches!(kind, "T" | "t").then(|| name.to_string())
+            })
+            .collect()
+    }
+
+    fn has_bpf_lsm(&self) -> bool {
+        fs::read_to_string(self.path("sys/kernel/security/lsm"))
+            .map(|lsm| lsm.trim().split(',').any(|m| m == "bpf"))
+            .unwrap_or(false)
+    }
+
+    /// Capability report for the configured sources.
+    pub fn probe(&self, ebpf: bool, auditd: bool) -> KernelCapabilities {
+        let kernel_release = self.kernel_release();
+        let btf = self.has_btf();
+        let functions = if ebpf && btf { self.kernel_functions() } else { HashSet::new() };
+        let hooks = HOOKS
+            .iter()
+            .map(|spec| {
+                let (attach_point, ebpf_reason) = if !ebpf {
+                    (None, "eBPF disabled".to_string())
+                } else if !btf {
+                    (None, "kernel has no BTF (CO-RE unavailable)".to_string())
+                } else {
+                    let found = spec.attach_points.iter().find(|point| match point {
+                        AttachPoint::Tracepoint(category, name) => self.has_tracepoint(category, name),
+                        AttachPoint::Kprobe(symbol) => functions.contains(*symbol),
+                    });
+                    let missing = spec.attach_points.iter().map(AttachPoint::describe).collect::<Vec<_>>().join(", ");
+                    (found.copied(), format!("no attach point ({})", missing))
+                };
+                match attach_point {
+                    Some(point) => HookStatus {
+                        hook: spec.hook,
+                        source: HookSource::Ebpf,
+                        attach_point: Some(point.describe()),
+                        reason: None,
+                    },
+                    None => HookStatus {
+                        hook: spec.hook,
+                        source: if auditd && !spec.audit_syscalls.is_empty() {
+                            HookSource::Auditd
+                        } else {
+                            HookSource::Unavailable
+                        },
+                        attach_point: None,
+                        reason: Some(ebpf_reason),
+                    },
+                }
+            })
+            .collect();
+        KernelCapabilities {
+            ringbuf: release_at_least(&kernel_release, (5, 8)),
+            bpf_lsm: self.has_bpf_lsm(),
+            kernel_release,
+            btf,
+            hooks,
+        }
+    }
+}
+
+/// "5.15.0-91-generic" >= (major, minor); unparsable releases are treated as older.
+fn release_at_least(release: &str, min: (u32, u32)) -> bool {
+    let mut parts = release.split(|c: char| !c.is_ascii_digit());
+    let major = parts.next().and_then(|p| p.parse::<u32>().ok());
+    let minor = parts.next().and_then(|p| p.parse::<u32>().ok());
+    match (major, minor) {
+        (Some(major), Some(minor)) => (major, minor) >= min,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn fake_kernel(release: &str, btf: bool, tracepoints: &[(&str, &str)], kallsyms: &str) -> TempDir {
+        let root = TempDir::new().unwrap();
+        let path = |p: &str| root.path().join(p);
+        fs::create_dir_all(path("proc/sys/kernel")).unwrap();
+        fs::write(path("proc/sys/kernel/osrelease"), format!("{}\n", release)).unwrap();
+        fs::write(path("proc/kallsyms"), kallsyms).unwrap();
+        if btf {
+            fs::create_dir_all(path("sys/kernel/btf")).unwrap();
+            fs::write(path("sys/kernel/btf/vmlinux"), b"BTF").unwrap();
+        }
+        for (category, name) in tracepoints {
+            fs::create_dir_all(path("sys/kernel/tracing/events").join(category).join(name)).unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn test_degrades_per_hook() {
+        let root = fake_kernel(
+            "5.15.0-91-generic",
+            true,
+            &[("sched", "sched_process_exec"), ("sched", "sched_process_fork")],
+            "ffffffff81000000 T vfs_write\nffffffff81000010 t tcp_connect\nffffffff81000020 D vfs_unlink\n",
+        );
+        let caps = KernelProber::with_root(root.path()).probe(true, true);
+
+        assert!(caps.btf && caps.ringbuf);
+        let exec = caps.hook(Hook::ProcessExec).unwrap();
+        assert_eq!(exec.source, HookSource::Ebpf);
+        assert_eq!(exec.attach_point.as_deref(), Some("tracepoint:sched/sched_process_exec"));
+        assert_eq!(caps.hook(Hook::FileWrite).unwrap().source, HookSource::Ebpf);
+        assert_eq!(caps.hook(Hook::NetConnect).unwrap().attach_point.as_deref(), Some("kprobe:tcp_connect"));
+        // Data symbol is not a kprobe target: unlink falls back to auditd
+        assert_eq!(caps.hook(Hook::FileUnlink).unwrap().source, HookSource::Auditd);
+        // No tracepoint and no auditd mapping
+        assert_eq!(caps.hook(Hook::ProcessExit).unwrap().source, HookSource::Unavailable);
+        assert!(caps.degraded());
+        assert!(audit_syscalls(&caps).contains(&"unlinkat"));
+        assert!(!audit_syscalls(&caps).contains(&"execve"));
+    }
+
+    #[test]
+    fn test_no_btf_means_no_ebpf_hooks() {
+        let root = fake_kernel("4.19.0", false, &[("sched", "sched_process_exec")], "ffffffff81000000 T vfs_write\n");
+        let caps = KernelProber::with_root(root.path()).probe(true, false);
+
+        assert!(!caps.btf && !caps.ringbuf);
+        assert_eq!(caps.count(HookSource::Ebpf), 0);
+        assert_eq!(caps.count(HookSource::Unavailable), caps.hooks.len());
+        assert!(caps.hook(Hook::ProcessExec).unwrap().reason.as_deref().unwrap().contains("BTF"));
+    }
+
+    #[test]
+    fn test_release_parsing() {
+        assert!(release_at_least("5.8.0", (5, 8)));
+        assert!(release_at_least("6.1.0-13-amd64", (5, 8)));
+        assert!(!release_at_least("5.4.0-150-generic", (5, 8)));
+        assert!(!release_at_least("unknown", (5, 8)));
+    }
+}
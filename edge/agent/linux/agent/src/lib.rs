@@ -9,32 +9,68 @@
 /// - Tracks process, filesystem, network events
 /// - Emits signed telemetry only
 /// 
-/// **CRITICAL**: This module is STAND-ALONE, NO enforcement, NO policy, NO kill-switch authority.
+/// **CRITICAL**: This module is STAND-ALONE, NO kill-switch authority. The only enforcement is
+/// the opt-in kill of blocklisted binaries (hash_policy, AGENT_HASH_POLICY_ENFORCE).
 /// Produces validated telemetry ONLY.
 
 pub mod errors;
 pub mod process;
+pub mod lineage;
 pub mod filesystem;
 pub mod network;
 pub mod syscalls;
+pub mod kernel_caps;
+pub mod container;
 pub mod features;
 pub mod envelope;
 pub mod backpressure;
 pub mod rate_limit;
 pub mod health;
 pub mod hardening;
+pub mod spool;
+pub mod spool_crypto;
+pub mod delivery;
+pub mod priority;
+pub mod drops;
+pub mod inventory;
+pub mod logfile;
+pub mod log_seal;
+pub mod disk_budget;
+pub mod pipeline;
+pub mod memory_acquisition;
+pub mod yara_scan;
+pub mod hash_policy;
+pub mod traffic;
+pub mod signing_pool;
 
 // Security module is in agent/security/
 
 pub use errors::AgentError;
 pub use process::ProcessMonitor;
+pub use lineage::{LineageChain, LineageLink};
 pub use filesystem::FilesystemMonitor;
 pub use network::NetworkMonitor;
 pub use syscalls::SyscallMonitor;
+pub use kernel_caps::{KernelCapabilities, KernelProber};
+pub use container::{ContainerContext, ContainerResolver};
 pub use features::FeatureExtractor;
 pub use envelope::EventEnvelope;
 pub use backpressure::BackpressureManager;
 pub use rate_limit::RateLimiter;
 pub use health::HealthMonitor;
 pub use hardening::RuntimeHardening;
+pub use spool::EventSpool;
+pub use spool_crypto::SpoolCipher;
+pub use delivery::{DeliveryManager, DeliveryConfig, DeliveryStats};
+pub use priority::{EventPriority, PriorityEventQueue};
+pub use drops::{DropLedger, DropReason, DropsByReason};
+pub use inventory::{HostInventory, InventoryCollector};
+pub use logfile::RotatingLogFile;
+pub use log_seal::{LogChain, LogShipper, SealedSegment};
+pub use disk_budget::{DiskBudget, DiskUsage};
+pub use pipeline::{DeliveryQueue, ShutdownSignal};
+pub use yara_scan::{YaraMatchData, YaraScanner};
+pub use hash_policy::{HashPolicyEvaluator, HashVerdict};
+pub use traffic::{BacklogWindows, BandwidthBudget, TelemetryClient};
+pub use signing_pool::{OrderedResults, PoolSubmitter};
 
@@ -0,0 +1,166 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_linux_agent/agent/src/lineage.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Process lineage hashing - per-boot hash chain linking every process event to the latest event of its parent so Core can detect missing or injected process events
+
+/*
+ * Process Lineage Chain
+ *
+ * Every process event carries:
+ *   hash        = SHA-256("ransomeye-lineage-v1" | boot_id | event_type | pid | ppid | uid | gid |
+ *                         executable | command_line | mmap_address | mmap_size | parent_hash)
+ *   parent_hash = latest event hash of the process this event descends from:
+ *                   fork  the parent pid
+ *                   exec  the pid itself when tracked (fork then exec), else the parent pid
+ *                   mmap  the pid itself
+ *                 or the chain's genesis hash when that process has no event yet (started
+ *                 before the agent, or evicted from the bounded process table)
+ *
+ * Fields are joined with 0x1f, absent values are empty. The genesis is
+ * SHA-256("ransomeye-lineage-genesis-v1" | boot_id | chain_id); chain_id is random per agent
+ * start, so an agent restart within one boot starts a new chain instead of orphaning the old one.
+ * Core verifies the chain per (agent, boot_id, chain_id): recomputed hash mismatch = altered or
+ * injected event, parent_hash never seen = missing event.
+ */
+
+use crypto::digest::Sha256;
+use tracing::warn;
+use uuid::Uuid;
+
+const BOOT_ID_PATH: &str = "/proc/sys/kernel/random/boot_id";
+const SEP: &str = "\x1f";
+
+/// Hashed fields of one process event.
+#[derive(Debug, Clone, Copy)]
+pub struct LineageInput<'a> {
+    pub event_type: &'a str,
+    pub pid: u32,
+    pub ppid: Option<u32>,
+    pub uid: u32,
+    pub gid: u32,
+    pub executable: Option<&'a str>,
+    pub command_line: Option<&'a str>,
+    pub mmap_address: Option<u64>,
+    pub mmap_size: Option<u64>,
+}
+
+/// Lineage link carried by a process event.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct LineageLink {
+    pub boot_id: String,
+    pub chain_id: String,
+    pub hash: String,
+    pub parent_hash: String,
+}
+
+/// One boot's chain for this agent run.
+#[derive(Debug, Clone)]
+pub struct LineageChain {
+    boot_id: String,
+    chain_id: String,
+    genesis: String,
+}
+
+fn opt<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+impl LineageChain {
+    pub fn new(boot_id: String, chain_id: String) -> Self {
+        let genesis = hex::encode(Sha256::digest(
+            ["ransomeye-lineage-genesis-v1", &boot_id, &chain_id].join(SEP).as_bytes(),
+        ));
+        Self { boot_id, chain_id, genesis }
+    }
+
+    /// Chain for the running kernel's boot. Without a readable boot_id a random one is used:
+    /// the chain stays verifiable, Core just sees it as a separate boot.
+    pub fn for_this_boot() -> Self {
+        let boot_id = match std::fs::read_to_string(BOOT_ID_PATH) {
+            Ok(id) if !id.trim().is_empty() => id.trim().to_string(),
+            _ => {
+                warn!("Cannot read {}; process lineage uses a random boot id", BOOT_ID_PATH);
+                Uuid::new_v4().to_string()
+            }
+        };
+        Self::new(boot_id, Uuid::new_v4().to_string())
+    }
+
+    pub fn genesis(&self) -> &str {
+        &self.genesis
+    }
+
+    /// Link an event to `parent_hash` (the genesis when `None`).
+    pub fn link(&self, input: &LineageInput<'_>, parent_hash: Option<&str>) -> LineageLink {
+        let parent_hash = parent_hash.unwrap_or(&self.genesis).to_string();
+        LineageLink {
+            boot_id: self.boot_id.clone(),
+            chain_id: self.chain_id.clone(),
+            hash: lineage_hash(&self.boot_id, input, &parent_hash),
+            parent_hash,
+        }
+    }
+}
+
+pub fn lineage_hash(boot_id: &str, input: &LineageInput<'_>, parent_hash: &str) -> String {
+    let fields = [
+        "ransomeye-lineage-v1".to_string(),
+        boot_id.to_string(),
+        input.event_type.to_string(),
+        input.pid.to_string(),
+        opt(input.ppid),
+        input.uid.to_string(),
+        input.gid.to_string(),
+        opt(input.executable),
+        opt(input.command_line),
+        opt(input.mmap_address),
+        opt(input.mmap_size),
+        parent_hash.to_string(),
+    ];
+    hex::encode(Sha256::digest(fields.join(SEP).as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exec(pid: u32, ppid: u32) -> LineageInput<'static> {
+        LineageInput {
+            event_type: "Exec",
+            pid,
+            ppid: Some(ppid),
+            uid: 1000,
+            gid: 1000,
+            executable: Some("/usr/bin/test"),
+            command_line: Some("test --arg"),
+            mmap_address: None,
+            mmap_size: None,
+        }
+    }
+
+    #[test]
+    fn test_links_chain_to_parent_and_genesis() {
+        let chain = LineageChain::new("boot-a".to_string(), "chain-1".to_string());
+        let root = chain.link(&exec(100, 1), None);
+        assert_eq!(root.parent_hash, chain.genesis());
+
+        let child = chain.link(&exec(200, 100), Some(&root.hash));
+        assert_eq!(child.parent_hash, root.hash);
+        assert_eq!(child.hash, lineage_hash("boot-a", &exec(200, 100), &root.hash));
+        assert_ne!(child.hash, chain.link(&exec(200, 100), None).hash);
+    }
+
+    #[test]
+    fn test_hash_matches_core_scheme() {
+        // Same vector as core/ingest/tests/lineage_tests.rs
+        let chain = LineageChain::new("boot-a".to_string(), "chain-1".to_string());
+        assert_eq!(chain.genesis(), "182b4c88692b8e3788d37aa54ccdebc02accc8d262850e97a7f87b885e3f25df");
+        assert_eq!(chain.link(&exec(100, 1), None).hash, "68813016eb2524c156722043ceaa4822e42c6cbcb794470ee36739e5bb1041d1");
+    }
+
+    #[test]
+    fn test_genesis_is_per_boot_and_chain() {
+        let a = LineageChain::new("boot-a".to_string(), "chain-1".to_string());
+        assert_ne!(a.genesis(), LineageChain::new("boot-b".to_string(), "chain-1".to_string()).genesis());
+        assert_ne!(a.genesis(), LineageChain::new("boot-a".to_string(), "chain-2".to_string()).genesis());
+    }
+}
@@ -0,0 +1,530 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_linux_agent/agent/src/log_seal.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tamper-evident agent logs - hash-chains every log line the agent writes, seals bounded segments with an Ed25519 signature and ships them to Core for the evidence store
+
+/*
+ * Log Sealing
+ *
+ * Off unless AGENT_LOG_SEALING is set. Every line the agent logs (log file or stdout) is also
+ * fed to a hash chain:
+ *
+ *   genesis   = SHA-256("ransomeye-log-genesis-v1" | boot_id | chain_id)
+ *   hash(n)   = SHA-256("ransomeye-log-line-v1" | hash(n-1) | line n)
+ *
+ * Fields are joined with 0x1f, hashes are lowercase hex, a line is the text without its newline
+ * (invalid UTF-8 replaced). chain_id is random per agent start. Lines are grouped into segments
+ * of at most AGENT_LOG_SEAL_SEGMENT_KB; every AGENT_LOG_SEAL_INTERVAL_SECS the open segment is
+ * closed and every closed segment sealed:
+ *
+ *   digest    = SHA-256("ransomeye-log-seal-v1" | chain_id | boot_id | segment_index | first_line |
+ *                       line_count | prev_hash | head_hash | sealed_at | segments_dropped_before)
+ *   signature = Ed25519(identity key, digest)
+ *
+ * prev_hash is the head of the previous segment (the genesis for segment 0), so segments link
+ * into one chain. Sealed segments wait in an outbox directory (AGENT_LOG_SEAL_OUTBOX_DIR) until
+ * Core accepts them (POST /agents/log-segments); Core verifies lines, signature and continuity and
+ * spools them for the evidence store.
+ *
+ * Logging never blocks on sealing: closed segments wait in a bounded queue, and when it is full
+ * the oldest is dropped. The next sealed segment carries segments_dropped_before, so a verifier can
+ * tell a reported drop from a segment removed later. Nothing here logs from inside the writer.
+ */
+
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{SecondsFormat, Utc};
+use crypto::digest::Sha256;
+use parking_lot::Mutex;
+use reqwest::Client as ReqwestClient;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use super::errors::AgentError;
+
+const BOOT_ID_PATH: &str = "/proc/sys/kernel/random/boot_id";
+const SEP: &str = "\x1f";
+const GENESIS_DOMAIN: &str = "ransomeye-log-genesis-v1";
+const LINE_DOMAIN: &str = "ransomeye-log-line-v1";
+const SEAL_DOMAIN: &str = "ransomeye-log-seal-v1";
+
+/// Closed segments waiting to be sealed; the oldest is dropped beyond this
+const MAX_PENDING_SEGMENTS: usize = 64;
+/// A line without a newline is cut here (a runaway write must not grow the buffer)
+const MAX_LINE_BYTES: usize = 64 * 1024;
+/// Per-request timeout of a segment upload
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const SEGMENT_FILE_EXT: &str = "json";
+
+/// Genesis hash of a chain
+pub fn genesis_hash(boot_id: &str, chain_id: &str) -> String {
+    hex::encode(Sha256::digest([GENESIS_DOMAIN, boot_id, chain_id].join(SEP).as_bytes()))
+}
+
+/// Hash of a line linked to the previous hash
+pub fn line_hash(prev_hash: &str, line: &str) -> String {
+    hex::encode(Sha256::digest([LINE_DOMAIN, prev_hash, line].join(SEP).as_bytes()))
+}
+
+/// A closed segment: consecutive lines of one chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingSegment {
+    pub segment_index: u64,
+    /// Chain position of the first line (0-based)
+    pub first_line: u64,
+    pub prev_hash: String,
+    pub head_hash: String,
+    pub lines: Vec<String>,
+    /// Segments dropped from the pending queue just before this one
+    pub segments_dropped_before: u64,
+}
+
+/// A signed segment, as written to the outbox and sent to Core
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SealedSegment {
+    pub chain_id: String,
+    pub boot_id: String,
+    pub segment_index: u64,
+    pub first_line: u64,
+    pub prev_hash: String,
+    pub head_hash: String,
+    /// RFC 3339, UTC; signed as written
+    pub sealed_at: String,
+    pub segments_dropped_before: u64,
+    pub lines: Vec<String>,
+    /// Hex Ed25519 public key of the agent identity
+    pub signer_key: String,
+    /// Base64 Ed25519 signature of the seal digest
+    pub signature: String,
+}
+
+impl SealedSegment {
+    /// Digest the signature covers; the lines are covered through head_hash
+    pub fn digest(&self) -> [u8; 32] {
+        let fields = [
+            SEAL_DOMAIN.to_string(),
+            self.chain_id.clone(),
+            self.boot_id.clone(),
+            self.segment_index.to_string(),
+            self.first_line.to_string(),
+            self.lines.len().to_string(),
+            self.prev_hash.clone(),
+            self.head_hash.clone(),
+            self.sealed_at.clone(),
+            self.segments_dropped_before.to_string(),
+        ];
+        Sha256::digest(fields.join(SEP).as_bytes())
+    }
+}
+
+/// Signs seal digests with the agent identity key
+pub trait SegmentSigner: Send + Sync {
+    fn public_key(&self) -> [u8; 32];
+    fn sign_digest(&self, digest: &[u8]) -> Result<String, AgentError>;
+}
+
+struct ChainState {
+    /// Bytes written since the last newline
+    partial: Vec<u8>,
+    head: String,
+    next_line: u64,
+    next_segment: u64,
+    open: Option<PendingSegment>,
+    open_bytes: usize,
+    pending: VecDeque<PendingSegment>,
+    dropped: u64,
+}
+
+/// Hash chain over the agent's log lines
+///
+/// Writable through `&LogChain`, so an `Arc` can be teed with the log writer of the tracing
+/// subscriber while the shipper takes closed segments.
+pub struct LogChain {
+    boot_id: String,
+    chain_id: String,
+    segment_bytes: usize,
+    state: Mutex<ChainState>,
+}
+
+impl LogChain {
+    pub fn new(boot_id: String, chain_id: String, segment_bytes: usize) -> Self {
+        let head = genesis_hash(&boot_id, &chain_id);
+        Self {
+            boot_id,
+            chain_id,
+            segment_bytes: segment_bytes.max(1),
+            state: Mutex::new(ChainState {
+                partial: Vec::new(),
+                head,
+                next_line: 0,
+                next_segment: 0,
+                open: None,
+                open_bytes: 0,
+                pending: VecDeque::new(),
+                dropped: 0,
+            }),
+        }
+    }
+
+    /// Chain for the running kernel's boot; a random boot id when it cannot be read
+    pub fn for_this_boot(segment_bytes: usize) -> Self {
+        let boot_id = match fs::read_to_string(BOOT_ID_PATH) {
+            Ok(id) if !id.trim().is_empty() => id.trim().to_string(),
+            _ => Uuid::new_v4().to_string(),
+        };
+        Self::new(boot_id, Uuid::new_v4().to_string(), segment_bytes)
+    }
+
+    pub fn boot_id(&self) -> &str {
+        &self.boot_id
+    }
+
+    pub fn chain_id(&self) -> &str {
+        &self.chain_id
+    }
+
+    /// Close the open segment and take every closed one, oldest first
+    pub fn take_segments(&self) -> Vec<PendingSegment> {
+        let mut state = self.state.lock();
+        Self::close_open(&mut state);
+        let mut segments: Vec<PendingSegment> = state.pending.drain(..).collect();
+        if let Some(first) = segments.first_mut() {
+            first.segments_dropped_before += std::mem::take(&mut state.dropped);
+        }
+        segments
+    }
+
+    fn append_line(&self, state: &mut ChainState, raw: &[u8]) {
+        let text = String::from_utf8_lossy(raw);
+        let line = text.strip_suffix('\r').unwrap_or(&text).to_string();
+        let hash = line_hash(&state.head, &line);
+        if state.open.is_none() {
+            state.open = Some(PendingSegment {
+                segment_index: state.next_segment,
+                first_line: state.next_line,
+                prev_hash: state.head.clone(),
+                head_hash: String::new(),
+                lines: Vec::new(),
+                segments_dropped_before: 0,
+            });
+            state.next_segment += 1;
+            state.open_bytes = 0;
+        }
+        state.open_bytes += line.len() + 1;
+        if let Some(open) = state.open.as_mut() {
+            open.lines.push(line);
+            open.head_hash = hash.clone();
+        }
+        state.head = hash;
+        state.next_line += 1;
+        if state.open_bytes >= self.segment_bytes {
+            Self::close_open(state);
+        }
+    }
+
+    fn close_open(state: &mut ChainState) {
+        let Some(segment) = state.open.take() else {
+            return;
+        };
+        if state.pending.len() >= MAX_PENDING_SEGMENTS {
+            state.pending.pop_front();
+            state.dropped += 1;
+        }
+        state.pending.push_back(segment);
+    }
+}
+
+impl Write for &LogChain {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut state = self.state.lock();
+        let mut rest = buf;
+        while let Some(pos) = rest.iter().position(|b| *b == b'\n') {
+            let mut line = std::mem::take(&mut state.partial);
+            line.extend_from_slice(&rest[..pos]);
+            self.append_line(&mut state, &line);
+            rest = &rest[pos + 1..];
+        }
+        state.partial.extend_from_slice(rest);
+        if state.partial.len() >= MAX_LINE_BYTES {
+            let line = std::mem::take(&mut state.partial);
+            self.append_line(&mut state, &line);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Sign a closed segment of `chain`
+pub fn seal(chain: &LogChain, segment: PendingSegment, signer: &dyn SegmentSigner) -> Result<SealedSegment, AgentError> {
+    let mut sealed = SealedSegment {
+        chain_id: chain.chain_id.clone(),
+        boot_id: chain.boot_id.clone(),
+        segment_index: segment.segment_index,
+        first_line: segment.first_line,
+        prev_hash: segment.prev_hash,
+        head_hash: segment.head_hash,
+        sealed_at: Utc::now().to_rfc3339_opts(SecondsFormat::Micros, true),
+        segments_dropped_before: segment.segments_dropped_before,
+        lines: segment.lines,
+        signer_key: hex::encode(signer.public_key()),
+        signature: String::new(),
+    };
+    sealed.signature = signer.sign_digest(&sealed.digest())?;
+    Ok(sealed)
+}
+
+#[derive(Debug, Clone)]
+pub struct LogShipperConfig {
+    pub outbox_dir: PathBuf,
+    /// Sealed segments kept unshipped; the oldest is deleted beyond this
+    pub outbox_max: usize,
+    pub interval: Duration,
+}
+
+/// Seals closed segments into the outbox and ships the outbox to Core in order
+pub struct LogShipper {
+    client: ReqwestClient,
+    core_api_url: String,
+    api_token: String,
+    config: LogShipperConfig,
+}
+
+/// Outbox file name: sealing time, then chain position, so names sort in shipping order
+fn outbox_name(segment: &SealedSegment) -> String {
+    let micros = chrono::DateTime::parse_from_rfc3339(&segment.sealed_at).map(|t| t.timestamp_micros()).unwrap_or(0);
+    format!("{:020}-{}-{:010}.{}", micros, segment.chain_id, segment.segment_index, SEGMENT_FILE_EXT)
+}
+
+fn write_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    let mut file = fs::File::create(&tmp)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)
+}
+
+impl LogShipper {
+    pub fn new(core_api_url: String, api_token: String, config: LogShipperConfig) -> Result<Self, AgentError> {
+        fs::create_dir_all(&config.outbox_dir).map_err(|e| {
+            AgentError::ConfigurationError(format!(
+                "Failed to create log seal outbox {}: {}",
+                config.outbox_dir.display(),
+                e
+            ))
+        })?;
+        let client = ReqwestClient::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| AgentError::ConfigurationError(format!("Failed to create HTTP client: {}", e)))?;
+        Ok(Self { client, core_api_url, api_token, config })
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.config.interval
+    }
+
+    /// Sealed segments in the outbox, oldest first
+    pub fn outbox(&self) -> Vec<PathBuf> {
+        outbox_files(&self.config.outbox_dir)
+    }
+
+    /// Seal every closed segment of `chain` into the outbox, then trim the outbox to its bound.
+    /// Returns the number of segments sealed.
+    pub fn seal_pending(&self, chain: &LogChain, signer: &dyn SegmentSigner) -> Result<usize, AgentError> {
+        let segments = chain.take_segments();
+        let count = segments.len();
+        for segment in segments {
+            let sealed = seal(chain, segment, signer)?;
+            let json = serde_json::to_vec(&sealed)
+                .map_err(|e| AgentError::ConfigurationError(format!("Failed to encode log segment: {}", e)))?;
+            write_atomic(&self.config.outbox_dir.join(outbox_name(&sealed)), &json).map_err(|e| {
+                AgentError::ConfigurationError(format!("Failed to write log segment to {}: {}", self.config.outbox_dir.display(), e))
+            })?;
+        }
+        let outbox = self.outbox();
+        let excess = outbox.len().saturating_sub(self.config.outbox_max);
+        for path in outbox.into_iter().take(excess) {
+            warn!("Log seal outbox full; deleting unshipped segment {}", path.display());
+            let _ = fs::remove_file(path);
+        }
+        Ok(count)
+    }
+
+    /// Send the outbox to Core in order until it is empty or Core is unreachable.
+    /// Returns the number of segments Core accepted.
+    pub async fn ship(&self) -> usize {
+        let mut shipped = 0;
+        for path in self.outbox() {
+            let body = match fs::read(&path) {
+                Ok(body) => body,
+                Err(e) => {
+                    warn!("Cannot read sealed log segment {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            let res = self
+                .client
+                .post(format!("{}/agents/log-segments", self.core_api_url))
+                .bearer_auth(&self.api_token)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body)
+                .send()
+                .await;
+            match res {
+                Ok(res) if res.status().is_success() => {
+                    let _ = fs::remove_file(&path);
+                    shipped += 1;
+                }
+                Ok(res) if res.status().is_client_error() && res.status() != reqwest::StatusCode::TOO_MANY_REQUESTS => {
+                    // Core refused this segment for good (verification or continuity); retrying cannot help
+                    error!("Core refused sealed log segment {}: HTTP {}", path.display(), res.status());
+                    let _ = fs::remove_file(&path);
+                }
+                Ok(res) => {
+                    warn!("Log segment upload deferred: HTTP {}", res.status());
+                    break;
+                }
+                Err(e) => {
+                    warn!("Log segment upload deferred: {}", e);
+                    break;
+                }
+            }
+        }
+        if shipped > 0 {
+            info!("Shipped {} sealed log segment(s) to Core", shipped);
+        }
+        shipped
+    }
+}
+
+fn outbox_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|rd| rd.flatten().map(|e| e.path()).collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|p| p.extension().and_then(|e| e.to_str()) == Some(SEGMENT_FILE_EXT))
+        .collect();
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::{engine::general_purpose, Engine as _};
+    use crypto::signature::{verify_ed25519, Ed25519KeyPair};
+    use tempfile::TempDir;
+
+    struct TestSigner(Ed25519KeyPair);
+
+    /// Core's line check: the lines lead from prev_hash to head_hash
+    fn verify_lines(segment: &SealedSegment) -> Result<(), String> {
+        let head = segment.lines.iter().fold(segment.prev_hash.clone(), |prev, line| line_hash(&prev, line));
+        if head != segment.head_hash {
+            return Err(format!("lines do not hash to head {}", segment.head_hash));
+        }
+        Ok(())
+    }
+
+    impl SegmentSigner for TestSigner {
+        fn public_key(&self) -> [u8; 32] {
+            self.0.public_key()
+        }
+
+        fn sign_digest(&self, digest: &[u8]) -> Result<String, AgentError> {
+            Ok(general_purpose::STANDARD.encode(self.0.sign(digest)))
+        }
+    }
+
+    fn chain(segment_bytes: usize) -> LogChain {
+        LogChain::new("boot-1".to_string(), "chain-1".to_string(), segment_bytes)
+    }
+
+    #[test]
+    fn test_lines_chain_across_writes_and_segments() {
+        let chain = chain(20);
+        (&chain).write_all(b"first line\nsec").unwrap();
+        (&chain).write_all(b"ond line\r\nthird line\n").unwrap();
+        (&chain).write_all(b"partial").unwrap();
+
+        let segments = chain.take_segments();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].lines, vec!["first line", "second line"]);
+        assert_eq!(segments[0].prev_hash, genesis_hash("boot-1", "chain-1"));
+        assert_eq!(segments[1].lines, vec!["third line"]);
+        assert_eq!(segments[1].first_line, 2);
+        assert_eq!(segments[1].segment_index, 1);
+        assert_eq!(segments[1].prev_hash, segments[0].head_hash);
+
+        // The unterminated line stays open until its newline arrives
+        (&chain).write_all(b" done\n").unwrap();
+        let next = chain.take_segments();
+        assert_eq!(next[0].lines, vec!["partial done"]);
+        assert_eq!(next[0].prev_hash, segments[1].head_hash);
+    }
+
+    #[test]
+    fn test_sealed_segment_verifies_and_detects_edits() {
+        let chain = chain(1024);
+        (&chain).write_all(b"agent started\nconnected to core\n").unwrap();
+        let signer = TestSigner(Ed25519KeyPair::generate().unwrap());
+        let segment = chain.take_segments().remove(0);
+        let sealed = seal(&chain, segment, &signer).unwrap();
+
+        assert!(verify_lines(&sealed).is_ok());
+        let signature = general_purpose::STANDARD.decode(&sealed.signature).unwrap();
+        assert!(verify_ed25519(&signer.public_key(), &sealed.digest(), &signature).is_ok());
+
+        let mut edited = sealed.clone();
+        edited.lines[1] = "connected to attacker".to_string();
+        assert!(verify_lines(&edited).is_err());
+        let mut removed = sealed.clone();
+        removed.lines.pop();
+        removed.head_hash = line_hash(&removed.prev_hash, &removed.lines[0]);
+        assert!(verify_lines(&removed).is_ok());
+        assert!(verify_ed25519(&signer.public_key(), &removed.digest(), &signature).is_err());
+    }
+
+    #[test]
+    fn test_full_queue_drops_oldest_and_reports_it() {
+        let chain = chain(1);
+        for n in 0..(MAX_PENDING_SEGMENTS + 3) {
+            (&chain).write_all(format!("line {}\n", n).as_bytes()).unwrap();
+        }
+        let segments = chain.take_segments();
+        assert_eq!(segments.len(), MAX_PENDING_SEGMENTS);
+        assert_eq!(segments[0].segment_index, 3);
+        assert_eq!(segments[0].segments_dropped_before, 3);
+        assert!(segments[1..].iter().all(|s| s.segments_dropped_before == 0));
+    }
+
+    #[test]
+    fn test_outbox_is_ordered_and_bounded() {
+        let dir = TempDir::new().unwrap();
+        let shipper = LogShipper::new("http://127.0.0.1:9".to_string(), "token".to_string(), LogShipperConfig {
+            outbox_dir: dir.path().to_path_buf(),
+            outbox_max: 2,
+            interval: Duration::from_secs(60),
+        })
+        .unwrap();
+        let chain = chain(1);
+        let signer = TestSigner(Ed25519KeyPair::generate().unwrap());
+        (&chain).write_all(b"a\nb\nc\n").unwrap();
+
+        assert_eq!(shipper.seal_pending(&chain, &signer).unwrap(), 3);
+        let outbox = shipper.outbox();
+        assert_eq!(outbox.len(), 2);
+        let indexes: Vec<u64> = outbox
+            .iter()
+            .map(|p| serde_json::from_slice::<SealedSegment>(&fs::read(p).unwrap()).unwrap().segment_index)
+            .collect();
+        assert_eq!(indexes, vec![1, 2]);
+    }
+}
@@ -0,0 +1,170 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_linux_agent/agent/src/logfile.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Agent-managed log file - size-based rotation with zstd-compressed archives and bounded retention
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use parking_lot::Mutex;
+
+use super::errors::AgentError;
+
+const ARCHIVE_EXT: &str = "zst";
+const ZSTD_LEVEL: i32 = 3;
+
+/// Rotating log file
+///
+/// Writes go to `<path>`. When it reaches `max_bytes` it is compressed to
+/// `<path>.<unix_nanos>.zst` and truncated; only the newest `keep` archives are kept.
+/// Writable through `&RotatingLogFile`, so an `Arc` can be handed to the tracing subscriber
+/// while the disk budget reads usage and prunes archives.
+pub struct RotatingLogFile {
+    path: PathBuf,
+    max_bytes: u64,
+    keep: usize,
+    inner: Mutex<LogInner>,
+}
+
+struct LogInner {
+    file: File,
+    written: u64,
+    /// Last archive stamp; stamps stay unique even if the clock does not advance
+    last_stamp: u128,
+}
+
+impl RotatingLogFile {
+    pub fn open(path: &Path, max_bytes: u64, keep: usize) -> Result<Self, AgentError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| AgentError::ConfigurationError(format!("Failed to create log dir {}: {}", parent.display(), e)))?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)
+            .map_err(|e| AgentError::ConfigurationError(format!("Failed to open log file {}: {}", path.display(), e)))?;
+        let written = file.metadata().map(|m| m.len()).unwrap_or(0);
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_bytes: max_bytes.max(1),
+            keep,
+            inner: Mutex::new(LogInner { file, written, last_stamp: 0 }),
+        })
+    }
+
+    /// Bytes on disk: active file plus compressed archives
+    pub fn usage_bytes(&self) -> u64 {
+        let active = self.inner.lock().written;
+        active + self.archives().iter().map(|(_, size)| size).sum::<u64>()
+    }
+
+    /// Delete oldest archives until total usage is at most `max_bytes` (the active file is never deleted)
+    pub fn prune_to(&self, max_bytes: u64) -> u64 {
+        let mut usage = self.usage_bytes();
+        let mut removed = 0;
+        for (path, size) in self.archives() {
+            if usage <= max_bytes {
+                break;
+            }
+            if fs::remove_file(&path).is_ok() {
+                usage -= size;
+                removed += 1;
+            }
+        }
+        removed
+    }
+
+    /// Archives oldest-first
+    fn archives(&self) -> Vec<(PathBuf, u64)> {
+        let (Some(dir), Some(name)) = (self.path.parent(), self.path.file_name().and_then(|n| n.to_str())) else {
+            return Vec::new();
+        };
+        let prefix = format!("{}.", name);
+        let mut archives: Vec<(u128, PathBuf, u64)> = fs::read_dir(dir)
+            .map(|rd| rd.flatten().collect::<Vec<_>>())
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|entry| {
+                let file_name = entry.file_name().to_str()?.to_string();
+                let stamp = file_name.strip_prefix(&prefix)?.strip_suffix(&format!(".{}", ARCHIVE_EXT))?.parse::<u128>().ok()?;
+                Some((stamp, entry.path(), entry.metadata().ok()?.len()))
+            })
+            .collect();
+        archives.sort_unstable_by_key(|(stamp, _, _)| *stamp);
+        archives.into_iter().map(|(_, path, size)| (path, size)).collect()
+    }
+
+    fn rotate(&self, inner: &mut LogInner) -> io::Result<()> {
+        inner.file.flush()?;
+        let raw = fs::read(&self.path)?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+        let stamp = now.max(inner.last_stamp + 1);
+        inner.last_stamp = stamp;
+        let archive = PathBuf::from(format!("{}.{}.{}", self.path.display(), stamp, ARCHIVE_EXT));
+        fs::write(&archive, zstd::encode_all(raw.as_slice(), ZSTD_LEVEL)?)?;
+        inner.file.set_len(0)?;
+        inner.written = 0;
+
+        let archives = self.archives();
+        let excess = archives.len().saturating_sub(self.keep);
+        for (path, _) in archives.into_iter().take(excess) {
+            let _ = fs::remove_file(path);
+        }
+        Ok(())
+    }
+}
+
+impl Write for &RotatingLogFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut inner = self.inner.lock();
+        if inner.written > 0 && inner.written + buf.len() as u64 > self.max_bytes {
+            // A failed rotation must not stop logging; the file keeps growing until the next attempt
+            let _ = self.rotate(&mut inner);
+        }
+        let n = inner.file.write(buf)?;
+        inner.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.lock().file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_rotates_compresses_and_keeps_newest() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("agent.log");
+        let log = RotatingLogFile::open(&path, 1024, 2).unwrap();
+        let line = vec![b'l'; 300];
+
+        for _ in 0..20 {
+            (&log).write_all(&line).unwrap();
+        }
+
+        let archives = log.archives();
+        assert_eq!(archives.len(), 2);
+        assert!(fs::metadata(&path).unwrap().len() <= 1024);
+        let restored = zstd::decode_all(fs::read(&archives[1].0).unwrap().as_slice()).unwrap();
+        assert_eq!(restored.len(), 900);
+        assert!(log.usage_bytes() < 20 * 300);
+    }
+
+    #[test]
+    fn test_prune_removes_archives_only() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("agent.log");
+        let log = RotatingLogFile::open(&path, 100, 10).unwrap();
+        for _ in 0..5 {
+            (&log).write_all(&[b'x'; 80]).unwrap();
+        }
+        assert_eq!(log.archives().len(), 4);
+
+        log.prune_to(0);
+        assert!(log.archives().is_empty());
+        assert_eq!(log.usage_bytes(), 80);
+    }
+}
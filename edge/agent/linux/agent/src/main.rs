@@ -3,20 +3,40 @@
 // Details of functionality of this file: Linux Agent main entry point - standalone host telemetry sensor
 
 use std::sync::Arc;
-use tracing::{info, error};
-use tokio::runtime::Runtime;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn, error};
+use tracing_subscriber::fmt::writer::MakeWriterExt;
+use tokio::sync::mpsc;
 
 mod errors;
 mod process;
+mod lineage;
 mod filesystem;
 mod network;
 mod syscalls;
+mod kernel_caps;
+mod container;
 mod features;
 mod envelope;
 mod backpressure;
 mod rate_limit;
 mod health;
 mod hardening;
+mod spool;
+mod spool_crypto;
+mod delivery;
+mod priority;
+mod drops;
+mod inventory;
+mod logfile;
+mod log_seal;
+mod disk_budget;
+mod pipeline;
+mod memory_acquisition;
+mod yara_scan;
+mod hash_policy;
+mod traffic;
+mod signing_pool;
 
 #[path = "../security/mod.rs"]
 mod security;
@@ -24,30 +44,106 @@ mod security;
 #[path = "../../config/validation.rs"]
 mod config_validation;
 
-// Import signing from parent src/
-#[path = "../../src/signing.rs"]
-mod signing;
-
 use errors::AgentError;
-use process::ProcessMonitor;
+use process::{ProcessEvent, ProcessEventType, ProcessMonitor};
 use filesystem::FilesystemMonitor;
 use network::NetworkMonitor;
 use syscalls::SyscallMonitor;
-use features::FeatureExtractor;
-use envelope::EnvelopeBuilder;
+use container::{ContainerResolver, KubeletClient};
+use features::{FeatureExtractor, Features};
+use envelope::{payload_digest, signed_event_body, AgentStatsData, EnvelopeBuilder, EventEnvelope};
 use backpressure::BackpressureManager;
 use rate_limit::RateLimiter;
 use health::HealthMonitor;
+use spool::EventSpool;
+use spool_crypto::{SpoolCipher, SPOOL_KEY_CONTEXT};
+use delivery::{DeliveryConfig, DeliveryManager, DeliveryOutcome};
+use priority::{EventPriority, PushOutcome};
+use drops::{DropLedger, DropReason};
+use inventory::{HostInventory, InventoryCollector};
+use logfile::RotatingLogFile;
+use log_seal::{LogChain, LogShipper, LogShipperConfig, SegmentSigner};
+use disk_budget::DiskBudget;
+use pipeline::{spawn_stage, DeliveryQueue, ShutdownSignal};
+use memory_acquisition::{AcquisitionCommand, MemoryAcquirer, MemoryAcquisitionConfig};
+use yara_scan::{parse_signers, ScanKind, YaraMatchData, YaraScanConfig, YaraScanner};
+use hash_policy::{ExecHash, HashPolicyConfig, HashPolicyEvaluator};
+use traffic::{BacklogWindows, TelemetryClient};
+use signing_pool::{OrderedResults, PoolSubmitter};
 use security::{IdentityManager, EventSigner as SecurityEventSigner};
-use config_validation::AgentConfig;
-use reqwest::Client as ReqwestClient;
+use config_validation::{AgentConfig, LogFileConfig};
+
+/// Build provenance (commit, Cargo.lock hash, SBOM); see build.rs
+static BUILD_INFO: build_info::BuildInfo = build_info::build_info!();
+
+/// Bound of the channels between pipeline stages (the delivery queue is bounded by AGENT_MAX_QUEUE_SIZE)
+const STAGE_CHANNEL_CAPACITY: usize = 1024;
+
+/// Tick of the synthetic process monitor
+const MONITOR_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Watchdog heartbeat, health check and kubelet refresh check
+const SUPERVISOR_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Runtime tamper checks and disk budget enforcement
+const RUNTIME_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Stats log lines and the agent_stats event
+const STATS_INTERVAL: Duration = Duration::from_secs(60);
 
-fn main() -> Result<(), AgentError> {
-    // Initialize tracing
-    tracing_subscriber::fmt::init();
+/// Host inventory report, also sent once at startup
+const INVENTORY_INTERVAL: Duration = Duration::from_secs(24 * 3600);
+
+/// After shutdown is triggered, queued events are still sent for this long, then spooled
+/// (systemd TimeoutStopSec is 30s)
+const SHUTDOWN_DELIVERY_GRACE: Duration = Duration::from_secs(10);
+
+#[tokio::main]
+async fn main() -> Result<(), AgentError> {
+    BUILD_INFO.handle_version_flag("agent-linux");
+
+    // Initialize tracing: stdout, or an agent-managed rotating log file (AGENT_LOG_FILE); with
+    // AGENT_LOG_SEALING every line is also fed to the log hash chain
+    let log_config = LogFileConfig::from_env()
+        .map_err(AgentError::ConfigurationError)?;
+    let log_chain = log_config.sealing
+        .then(|| Arc::new(LogChain::for_this_boot(log_config.seal_segment_kb * 1024)));
+    let log_file = match log_config.path.as_ref() {
+        Some(path) => {
+            let log = Arc::new(RotatingLogFile::open(
+                std::path::Path::new(path),
+                log_config.max_mb * 1024 * 1024,
+                log_config.keep,
+            )?);
+            match log_chain.clone() {
+                Some(chain) => tracing_subscriber::fmt().with_ansi(false).with_writer(log.clone().and(chain)).init(),
+                None => tracing_subscriber::fmt().with_ansi(false).with_writer(log.clone()).init(),
+            }
+            Some(log)
+        }
+        None => {
+            match log_chain.clone() {
+                Some(chain) => tracing_subscriber::fmt().with_ansi(false).with_writer(std::io::stdout.and(chain)).init(),
+                None => tracing_subscriber::fmt::init(),
+            }
+            None
+        }
+    };
     
     info!("RansomEye Linux Agent starting...");
     
+    // Crypto backend self-check (FAIL-CLOSED): known answers, and FIPS mode for FIPS builds
+    crypto::self_check()
+        .map_err(|e| AgentError::ConfigurationError(format!("Crypto self-check failed: {}", e)))?;
+    info!("Crypto backend: {}", crypto::backend());
+    
+    // FAIL-CLOSED: a release deployment only runs the exact build its release manifest lists
+    match BUILD_INFO.verify_release_from_env("agent-linux") {
+        Ok(Some(release)) => info!("Build verified against release {} | commit={}", release, BUILD_INFO.git_commit),
+        Ok(None) => warn!("RANSOMEYE_RELEASE_MANIFEST not set; build commit={} not verified against a release", BUILD_INFO.git_commit),
+        Err(e) => return Err(AgentError::ConfigurationError(format!("Release verification failed: {}", e))),
+    }
+    
     // Get binary path for integrity verification
     let binary_path = std::env::current_exe()
         .map_err(|e| AgentError::ConfigurationError(format!("Failed to get binary path: {}", e)))?
@@ -84,6 +180,8 @@ fn main() -> Result<(), AgentError> {
     
     config.validate()
         .map_err(|e| AgentError::ConfigurationError(e))?;
+    log_config.validate(&config)
+        .map_err(AgentError::ConfigurationError)?;
     
     info!("Configuration loaded: max_processes={}, max_connections={}", 
         config.max_processes, config.max_connections);
@@ -124,16 +222,128 @@ fn main() -> Result<(), AgentError> {
     let security_signer = Arc::new(security_signer);
     info!("Event signer created with Ed25519 key");
     
-    // Initialize reqwest HTTP client for direct telemetry delivery
-    let http_client = ReqwestClient::builder()
-        .timeout(std::time::Duration::from_secs(10))
-        .build()
-        .map_err(|e| AgentError::ConfigurationError(format!("Failed to create HTTP client: {}", e)))?;
+    // HTTP client for direct telemetry delivery (DSCP-marked connections when AGENT_DSCP is set)
+    let http_client = TelemetryClient::new(config.dscp, std::time::Duration::from_secs(10))?;
+    let backlog_windows = BacklogWindows::parse(&config.backlog_windows)
+        .map_err(|e| AgentError::ConfigurationError(format!("AGENT_BACKLOG_WINDOWS: {}", e)))?;
     
     let core_api_url = config.core_api_url.clone();
-    info!("HTTP client initialized for direct delivery to {}", core_api_url);
+    info!("HTTP client initialized for direct delivery to {} (dscp={})", core_api_url,
+        http_client.dscp().map_or_else(|| "unset".to_string(), |d| d.to_string()));
+    
+    // Per-agent ingest bearer token (issued at enrollment). FAIL-CLOSED if configured but unreadable.
+    let api_token: Option<String> = match config.api_token_path.as_ref() {
+        Some(path) => {
+            let token = std::fs::read_to_string(path)
+                .map_err(|e| AgentError::ConfigurationError(format!("Failed to read AGENT_API_TOKEN_PATH {}: {}", path, e)))?
+                .trim()
+                .to_string();
+            if token.is_empty() {
+                return Err(AgentError::ConfigurationError(format!("Ingest API token file {} is empty", path)));
+            }
+            info!("Ingest API token loaded from {}", path);
+            Some(token)
+        }
+        None => None,
+    };
     info!("Core API URL: {}", core_api_url);
     
+    // Operator-triggered memory acquisition (off by default; validation requires the API token)
+    let memory_acquirer = match (config.memory_acquisition, api_token.as_ref()) {
+        (true, Some(token)) => {
+            let parse = |c: &Option<String>| c.as_deref().map(AcquisitionCommand::parse).transpose();
+            let acquirer = MemoryAcquirer::new(core_api_url.clone(), token.clone(), MemoryAcquisitionConfig {
+                full_command: parse(&config.memory_full_command)?,
+                process_command: parse(&config.memory_process_command)?,
+                work_dir: std::path::PathBuf::from(&config.memory_work_dir),
+                poll_interval: Duration::from_secs(config.memory_poll_secs),
+                tool_timeout: Duration::from_secs(config.memory_tool_timeout_secs),
+            })?;
+            info!("Memory acquisition enabled (full={}, process={}, poll {}s)",
+                config.memory_full_command.is_some(), config.memory_process_command.is_some(), config.memory_poll_secs);
+            Some(acquirer)
+        }
+        _ => None,
+    };
+    
+    // Tamper-evident log shipping (off by default; validation requires the API token)
+    let log_shipper = match (log_chain, api_token.as_ref()) {
+        (Some(chain), Some(token)) => {
+            let shipper = LogShipper::new(core_api_url.clone(), token.clone(), LogShipperConfig {
+                outbox_dir: std::path::PathBuf::from(&log_config.seal_outbox_dir),
+                outbox_max: log_config.seal_outbox_max,
+                interval: Duration::from_secs(log_config.seal_interval_secs),
+            })?;
+            info!("Log sealing enabled (chain={}, boot={}, segment {} KB, interval {}s, outbox {})",
+                chain.chain_id(), chain.boot_id(), log_config.seal_segment_kb, log_config.seal_interval_secs,
+                log_config.seal_outbox_dir);
+            Some((shipper, chain))
+        }
+        _ => None,
+    };
+    
+    // YARA scanning with signed rule packs (off by default; validation requires the API token)
+    let yara_scanner = match (config.yara_scanning, api_token.as_ref()) {
+        (true, Some(token)) => {
+            let scanner = YaraScanner::new(core_api_url.clone(), token.clone(), YaraScanConfig {
+                command: std::path::PathBuf::from(&config.yara_command),
+                signers: parse_signers(&config.yara_signers)?,
+                scan_paths: config.yara_scan_paths.iter().map(std::path::PathBuf::from).collect(),
+                scan_interval: Duration::from_secs(config.yara_scan_interval_secs),
+                cpu_percent: config.yara_cpu_percent,
+                max_file_bytes: config.yara_max_file_mb * 1024 * 1024,
+                work_dir: std::path::PathBuf::from(&config.yara_work_dir),
+                poll_interval: Duration::from_secs(config.yara_poll_secs),
+                file_timeout: Duration::from_secs(config.yara_file_timeout_secs),
+            })?;
+            info!("YARA scanning enabled (paths={}, interval {}s, cpu {}%)",
+                config.yara_scan_paths.join(","), config.yara_scan_interval_secs, config.yara_cpu_percent);
+            Some(scanner)
+        }
+        _ => None,
+    };
+    
+    // Binary hash allowlist/blocklist (off by default; FAIL-CLOSED on a policy that does not verify)
+    let hash_policy = match config.hash_policy_path.as_ref() {
+        Some(path) => Some(Arc::new(HashPolicyEvaluator::new(HashPolicyConfig {
+            path: std::path::PathBuf::from(path),
+            signers: hash_policy::parse_signers(&config.hash_policy_signers)?,
+            enforce: config.hash_policy_enforce,
+            max_file_bytes: config.hash_max_file_mb * 1024 * 1024,
+        })?)),
+        None => None,
+    };
+    
+    // Delivery layer: retry budget, jittered backoff, circuit breaker, local spool (FAIL-CLOSED if spool unusable)
+    // Spool is encrypted at rest under a key derived from the identity (signing) key
+    let spool_cipher = SpoolCipher::new(&security_signer.derive_key(SPOOL_KEY_CONTEXT)?);
+    let spool = EventSpool::open(std::path::Path::new(&config.spool_dir), config.spool_max_mb, spool_cipher)?
+        .with_segment_kb(config.spool_segment_kb);
+    let delivery = DeliveryManager::new(http_client, DeliveryConfig {
+        endpoint_url: format!("{}/ingest/linux", core_api_url),
+        api_token,
+        max_attempts: config.delivery_max_attempts,
+        backoff_base: std::time::Duration::from_millis(config.delivery_backoff_base_ms),
+        backoff_max: std::time::Duration::from_millis(config.delivery_backoff_max_ms),
+        retry_budget_tokens: config.delivery_retry_budget,
+        retry_budget_refill: config.delivery_retry_refill,
+        circuit_failure_threshold: config.circuit_failure_threshold,
+        circuit_open_duration: std::time::Duration::from_secs(config.circuit_open_secs),
+        spool_drain_batch: 100,
+        bandwidth_bytes_per_sec: config.bandwidth_bytes_per_sec,
+        bandwidth_burst_bytes: config.bandwidth_burst_bytes,
+        backlog_windows,
+    }, spool);
+    info!("Delivery layer initialized: spool={} ({} MB max), circuit threshold={}, bandwidth={} B/s (0 = unlimited), backlog windows={}", 
+        config.spool_dir, config.spool_max_mb, config.circuit_failure_threshold, config.bandwidth_bytes_per_sec,
+        if config.backlog_windows.is_empty() { "any time" } else { config.backlog_windows.as_str() });
+    
+    // Global disk budget (spool + managed logs); applied now and with the periodic runtime checks
+    let disk_budget = DiskBudget::new(config.disk_budget_mb);
+    let disk_usage = disk_budget.enforce(delivery.spool(), log_file.as_deref());
+    info!("Disk budget: {} MB (spool={} bytes, logs={} bytes)", 
+        config.disk_budget_mb, disk_usage.spool_bytes, disk_usage.log_bytes);
+    
     // CRITICAL: TLS/identity initialization MUST only occur for HTTPS URLs
     // If TransportClient or TLS initialization is added in the future, it must be gated:
     // if core_api_url.starts_with("https://") {
@@ -147,16 +357,32 @@ fn main() -> Result<(), AgentError> {
     // Initialize components
     let process_monitor = Arc::new(ProcessMonitor::new(config.max_processes));
     let _fs_monitor = Arc::new(FilesystemMonitor::new(config.mass_write_threshold));
-    let network_monitor = Arc::new(NetworkMonitor::new(config.max_connections));
+    let network_monitor = Arc::new(NetworkMonitor::with_host_root(config.max_connections, &config.host_root));
     let syscall_monitor = Arc::new(SyscallMonitor::new());
     let feature_extractor = Arc::new(FeatureExtractor::new());
-    let mut envelope_builder = EnvelopeBuilder::new(
+    let container_resolver = ContainerResolver::new(&config.host_root);
+    let kubelet = match config.kubelet_url.as_ref() {
+        Some(url) => {
+            info!("Kubelet pod metadata from {} (refresh {}s)", url, config.kubelet_refresh_secs);
+            Some(KubeletClient::new(
+                url.clone(),
+                config.kubelet_token_path.as_deref(),
+                config.kubelet_ca_path.as_deref(),
+                std::time::Duration::from_secs(config.kubelet_refresh_secs),
+            )?)
+        }
+        None => None,
+    };
+    let envelope_builder = EnvelopeBuilder::new(
         "linux_agent".to_string(),
         identity.component_id().to_string(),
     );
     let backpressure = Arc::new(BackpressureManager::new(config.max_queue_size));
+    let event_queue: Arc<DeliveryQueue<(String, Vec<u8>)>> = Arc::new(DeliveryQueue::new(config.max_queue_size));
     let rate_limiter = Arc::new(RateLimiter::new(config.rate_limit_tokens, config.rate_limit_refill));
+    let drops = Arc::new(DropLedger::new());
     let health_monitor = Arc::new(HealthMonitor::new(300)); // 5 minute max idle
+    health_monitor.record_disk(disk_usage);
     
     // Initialize syscall monitoring
     if config.enable_ebpf {
@@ -170,6 +396,10 @@ fn main() -> Result<(), AgentError> {
             }
         } else {
             info!("eBPF syscall monitoring initialized");
+            // Per-hook degradation: auditd serves the hooks this kernel cannot attach
+            if config.enable_auditd && syscall_monitor.capabilities().is_some_and(|c| c.degraded()) {
+                syscall_monitor.init_auditd()?;
+            }
         }
     } else if config.enable_auditd {
         syscall_monitor.init_auditd()?;
@@ -180,162 +410,626 @@ fn main() -> Result<(), AgentError> {
     info!("About to start syscall monitoring...");
     syscall_monitor.start()?;
     info!("Syscall monitoring started");
+    if let Some(caps) = syscall_monitor.capabilities() {
+        health_monitor.record_kernel_capabilities(caps);
+    }
     
     info!("Linux Agent started successfully");
     
-    // Create tokio runtime for async transport calls
-    let rt = Runtime::new()
-        .map_err(|e| AgentError::ConfigurationError(format!("Failed to create runtime: {}", e)))?;
+    // Pipeline: monitors -> features -> signing -> signing pool -> admission -> priority queue
+    // -> delivery, one task per stage (the pool runs on its own threads). Shutdown stops the
+    // monitors; every later stage drains its input and exits.
+    let shutdown = ShutdownSignal::new();
+    let container_resolver = Arc::new(container_resolver);
+    let delivery = Arc::new(delivery);
+    let (raw_tx, raw_rx) = mpsc::channel::<ProcessEvent>(STAGE_CHANNEL_CAPACITY);
+    let (sign_tx, sign_rx) = mpsc::channel::<SignRequest>(STAGE_CHANNEL_CAPACITY);
+    let (pool, signed) = {
+        let signer = security_signer.clone();
+        signing_pool::start(config.signing_workers, STAGE_CHANNEL_CAPACITY,
+            move |job| sign_job(&signer, &component_id, job))
+    };
+    info!("Signing pool: {} worker thread(s)", config.signing_workers);
+    
+    let mut stages = vec![
+        ("process_monitor", spawn_stage("process_monitor", &shutdown,
+            process_monitor_stage(process_monitor.clone(), rate_limiter, drops.clone(), raw_tx, shutdown.clone()))),
+        ("feature_extraction", spawn_stage("feature_extraction", &shutdown,
+            feature_stage(feature_extractor, hash_policy.clone(), raw_rx, sign_tx.clone()))),
+        ("signing", spawn_stage("signing", &shutdown, signing_stage(SigningStage {
+            envelope_builder,
+            signer: security_signer.clone(),
+            container_resolver: container_resolver.clone(),
+            queue: event_queue.clone(),
+            backpressure: backpressure.clone(),
+            health_monitor: health_monitor.clone(),
+            drops: drops.clone(),
+            pool,
+        }, sign_rx))),
+        ("admission", spawn_stage("admission", &shutdown,
+            admission_stage(signed, event_queue.clone(), drops.clone()))),
+        ("delivery", spawn_stage("delivery", &shutdown,
+            delivery_stage(delivery.clone(), event_queue.clone(), health_monitor.clone(), drops.clone(), shutdown.clone()))),
+    ];
+    if let Some(kubelet) = kubelet {
+        stages.push(("kubelet", spawn_stage("kubelet", &shutdown,
+            kubelet_stage(kubelet, container_resolver, shutdown.clone()))));
+    }
+    if let Some(acquirer) = memory_acquirer {
+        stages.push(("memory_acquisition", spawn_stage("memory_acquisition", &shutdown,
+            memory_acquisition_stage(acquirer, shutdown.clone()))));
+    }
+    if let Some((shipper, chain)) = log_shipper {
+        stages.push(("log_seal", spawn_stage("log_seal", &shutdown,
+            log_seal_stage(shipper, chain, security_signer.clone(), shutdown.clone()))));
+    }
+    if let Some(policy) = hash_policy {
+        stages.push(("hash_policy", spawn_stage("hash_policy", &shutdown,
+            hash_policy_stage(policy, Duration::from_secs(config.hash_policy_reload_secs), shutdown.clone()))));
+    }
+    if let Some(scanner) = yara_scanner {
+        stages.push(("yara_scan", spawn_stage("yara_scan", &shutdown,
+            yara_scan_stage(scanner, sign_tx.clone(), health_monitor.clone(), shutdown.clone()))));
+    }
+    
+    {
+        let shutdown = shutdown.clone();
+        tokio::spawn(async move {
+            match pipeline::wait_for_termination().await {
+                Ok(signal) => info!("{} received, shutting down", signal),
+                Err(e) => error!("{}, shutting down", e),
+            }
+            shutdown.trigger();
+        });
+    }
+    
+    // Supervisor: watchdog heartbeat, runtime checks, disk budget, health and stats
+    let supervised = supervise(Supervisor {
+        hardening: &hardening,
+        health_monitor: &health_monitor,
+        component_id: identity.component_id(),
+        disk_budget: &disk_budget,
+        delivery: &delivery,
+        log_file: log_file.as_deref(),
+        queue: &event_queue,
+        backpressure: &backpressure,
+        process_monitor: &process_monitor,
+        network_monitor: &network_monitor,
+        drops: &drops,
+        inventory: &InventoryCollector::new(&config.host_root),
+        stats_tx: sign_tx,
+    }, &shutdown).await;
+    shutdown.trigger();
+    // No more heartbeats from here; the drain is bounded by SHUTDOWN_DELIVERY_GRACE
+    hardening.stop_watchdog();
+    syscall_monitor.stop();
     
-    // Main processing loop
-    let mut event_count = 0u64;
+    let drained = pipeline::join_stages(stages).await;
+    supervised?;
+    drained?;
+    info!("Linux Agent stopped");
+    Ok(())
+}
+
+/// Input of the signing stage
+enum SignRequest {
+    Process(ProcessEvent, Features, Option<ExecHash>),
+    Stats(AgentStatsData),
+    Inventory(HostInventory),
+    YaraMatch(YaraMatchData),
+}
+
+impl From<YaraMatchData> for SignRequest {
+    fn from(hit: YaraMatchData) -> Self {
+        SignRequest::YaraMatch(hit)
+    }
+}
+
+/// Emits process events. Until the syscall sources feed this stage, it emits a synthetic exec
+/// event per tick so the pipeline stays exercised end to end.
+async fn process_monitor_stage(
+    process_monitor: Arc<ProcessMonitor>,
+    rate_limiter: Arc<RateLimiter>,
+    drops: Arc<DropLedger>,
+    tx: mpsc::Sender<ProcessEvent>,
+    shutdown: ShutdownSignal,
+) -> Result<(), AgentError> {
+    let mut ticker = tokio::time::interval(MONITOR_INTERVAL);
+    let mut emitted = 0u64;
+    loop {
+        tokio::select! {
+            _ = shutdown.wait() => return Ok(()),
+            _ = ticker.tick() => {}
+        }
+        
+        if !rate_limiter.allow()? {
+            drops.record(DropReason::RateLimited);
+            continue;
+        }
+        
+        // Simulate process exec event
+        let process_event = process_monitor.record_exec(
+            (1234 + (emitted % 10000)) as u32,
+            Some(1000),
+            1000,
+            1000,
+            "/usr/bin/test".to_string(),
+            Some("test --arg".to_string()),
+        )?;
+        emitted += 1;
+        
+        if tx.send(process_event).await.is_err() {
+            return Ok(());
+        }
+    }
+}
+
+/// Extracts features and, with a hash policy, hashes and judges each executed binary (off the
+/// async workers: hashing reads the file)
+async fn feature_stage(
+    feature_extractor: Arc<FeatureExtractor>,
+    hash_policy: Option<Arc<HashPolicyEvaluator>>,
+    mut rx: mpsc::Receiver<ProcessEvent>,
+    tx: mpsc::Sender<SignRequest>,
+) -> Result<(), AgentError> {
+    while let Some(process_event) = rx.recv().await {
+        let features = feature_extractor.extract_from_process(&process_event)?;
+        let exec_hash = match (&hash_policy, &process_event.event_type, process_event.executable.clone()) {
+            (Some(policy), ProcessEventType::Exec, Some(executable)) => {
+                let (policy, pid) = (policy.clone(), process_event.pid);
+                tokio::task::spawn_blocking(move || policy.evaluate_exec(pid, &executable))
+                    .await
+                    .map_err(|e| AgentError::PipelineFailed(format!("hash policy: {}", e)))?
+            }
+            _ => None,
+        };
+        if tx.send(SignRequest::Process(process_event, features, exec_hash)).await.is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+struct SigningStage {
+    envelope_builder: EnvelopeBuilder,
+    signer: Arc<SecurityEventSigner>,
+    container_resolver: Arc<ContainerResolver>,
+    queue: Arc<DeliveryQueue<(String, Vec<u8>)>>,
+    backpressure: Arc<BackpressureManager>,
+    health_monitor: Arc<HealthMonitor>,
+    drops: Arc<DropLedger>,
+    pool: PoolSubmitter<SigningJob>,
+}
+
+/// An admitted envelope on its way through the signing pool. Both signer sequence numbers are
+/// reserved by the signing stage, so they follow the envelope sequence whatever worker signs.
+struct SigningJob {
+    envelope: EventEnvelope,
+    priority: EventPriority,
+    /// Serialized event data covered by the envelope signature
+    payload: Vec<u8>,
+    payload_sequence: u64,
+    delivery_sequence: u64,
+}
+
+/// Signing pool output: the SignedEvent for /ingest/linux, queued by the admission stage
+type SignedJob = Result<(EventPriority, String, Vec<u8>), AgentError>;
+
+/// Builds envelopes (the envelope builder owns the sequence, so this stage is the only one
+/// touching it), sheds under backpressure and hands the rest to the signing pool
+async fn signing_stage(mut stage: SigningStage, mut rx: mpsc::Receiver<SignRequest>) -> Result<(), AgentError> {
+    while let Some(request) = rx.recv().await {
+        // Backpressure on the real queue depth; under pressure only Critical and
+        // ProcessExec events are admitted, lower priorities are shed (and counted) first
+        let queue_size = stage.queue.len();
+        stage.backpressure.update_queue_size(queue_size);
+        let under_pressure = stage.backpressure.should_drop(queue_size);
+        if under_pressure {
+            stage.backpressure.signal();
+        }
+        
+        // The envelope signature is filled in by the pool
+        let payload_sequence = stage.signer.reserve_sequence();
+        let (envelope, payload) = match request {
+            SignRequest::Process(process_event, features, exec_hash) => {
+                let envelope_data = serde_json::to_vec(&process_event)
+                    .map_err(|e| AgentError::EnvelopeCreationFailed(format!("{}", e)))?;
+                let envelope = stage.envelope_builder.build_from_process(&process_event, &features, String::new())?
+                    .with_container(stage.container_resolver.resolve(process_event.pid))
+                    .with_exec_hash(exec_hash);
+                stage.health_monitor.record_event();
+                info!("Event envelope created: {} (sequence: {})", envelope.event_id, envelope.sequence);
+                (envelope, envelope_data)
+            }
+            SignRequest::Stats(stats) => {
+                let stats_bytes = serde_json::to_vec(&stats)
+                    .map_err(|e| AgentError::EnvelopeCreationFailed(format!("{}", e)))?;
+                (stage.envelope_builder.build_agent_stats(std::process::id(), stats, String::new())?, stats_bytes)
+            }
+            SignRequest::Inventory(inventory) => {
+                let inventory_bytes = serde_json::to_vec(&inventory)
+                    .map_err(|e| AgentError::EnvelopeCreationFailed(format!("{}", e)))?;
+                (stage.envelope_builder.build_host_inventory(std::process::id(), inventory, String::new())?, inventory_bytes)
+            }
+            SignRequest::YaraMatch(hit) => {
+                let hit_bytes = serde_json::to_vec(&hit)
+                    .map_err(|e| AgentError::EnvelopeCreationFailed(format!("{}", e)))?;
+                (stage.envelope_builder.build_yara_match(std::process::id(), hit, String::new())?, hit_bytes)
+            }
+        };
+        
+        let priority = EventPriority::classify(&envelope);
+        if under_pressure && priority > EventPriority::ProcessExec {
+            stage.queue.record_drop(priority);
+            stage.drops.record(DropReason::Backpressure);
+            continue;
+        }
+        let job = SigningJob {
+            envelope,
+            priority,
+            payload,
+            payload_sequence,
+            delivery_sequence: stage.signer.reserve_sequence(),
+        };
+        if !stage.pool.submit(job).await {
+            return Err(AgentError::PipelineFailed("signing pool stopped".to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// Pool worker: envelope signature, canonical bytes, payload hash and delivery signature
+fn sign_job(signer: &SecurityEventSigner, signer_id: &str, job: SigningJob) -> SignedJob {
+    let mut envelope = job.envelope;
+    envelope.signature = signer.sign_at(job.payload_sequence, &job.payload);
+    let signed_event = sign_for_delivery(&envelope, signer, job.delivery_sequence, signer_id)?;
+    Ok((job.priority, envelope.event_id, signed_event))
+}
+
+/// Admits signed events to the delivery queue in envelope sequence order, as the pool releases them
+async fn admission_stage(
+    mut signed: OrderedResults<SignedJob>,
+    queue: Arc<DeliveryQueue<(String, Vec<u8>)>>,
+    drops: Arc<DropLedger>,
+) -> Result<(), AgentError> {
+    let result = async {
+        while let Some(job) = signed.next().await {
+            let (priority, event_id, signed_event) = job?;
+            match queue.push(priority, (event_id.clone(), signed_event)) {
+                PushOutcome::Queued => {}
+                PushOutcome::Evicted(_) => drops.record(DropReason::QueueFull),
+                PushOutcome::Dropped => {
+                    drops.record(DropReason::QueueFull);
+                    warn!("Event {} dropped: queue full ({} priority)", event_id, priority.as_str());
+                }
+            }
+        }
+        Ok(())
+    }.await;
+    // Also on failure, so delivery drains what was already admitted
+    queue.close();
+    result
+}
+
+/// Delivers queued events highest priority first until the queue is closed and empty. Past the
+/// shutdown grace period, remaining events are spooled instead of sent.
+async fn delivery_stage(
+    delivery: Arc<DeliveryManager>,
+    queue: Arc<DeliveryQueue<(String, Vec<u8>)>>,
+    health_monitor: Arc<HealthMonitor>,
+    drops: Arc<DropLedger>,
+    shutdown: ShutdownSignal,
+) -> Result<(), AgentError> {
+    let mut spooled_on_shutdown = 0u64;
+    while let Some((_, (event_id, signed_event))) = queue.next().await {
+        let outcome = if shutdown.grace_expired(SHUTDOWN_DELIVERY_GRACE) {
+            spooled_on_shutdown += 1;
+            delivery.spool_only(&signed_event)
+        } else {
+            delivery.deliver(&signed_event, &event_id).await
+        };
+        match outcome {
+            Ok(DeliveryOutcome::Delivered) | Ok(DeliveryOutcome::Rejected(_)) => {}
+            Ok(DeliveryOutcome::Spooled) => {
+                debug!("Event {} spooled (circuit: {})", event_id, delivery.stats().circuit_state.as_str());
+            }
+            Err(e) => {
+                drops.record(DropReason::SpoolFailed);
+                error!("Failed to deliver or spool event {}: {}", event_id, e);
+            }
+        }
+        health_monitor.record_delivery(delivery.stats());
+    }
+    if spooled_on_shutdown > 0 {
+        info!("Shutdown: {} queued events spooled for delivery after restart", spooled_on_shutdown);
+    }
+    Ok(())
+}
+
+/// Kubelet pod index; a failed refresh keeps the previous one
+async fn kubelet_stage(
+    kubelet: KubeletClient,
+    container_resolver: Arc<ContainerResolver>,
+    shutdown: ShutdownSignal,
+) -> Result<(), AgentError> {
+    let mut ticker = tokio::time::interval(SUPERVISOR_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = shutdown.wait() => return Ok(()),
+            _ = ticker.tick() => {}
+        }
+        if !kubelet.due() {
+            continue;
+        }
+        tokio::select! {
+            _ = shutdown.wait() => return Ok(()),
+            res = kubelet.fetch() => match res {
+                Ok(pods) => container_resolver.update_pods(pods),
+                Err(e) => warn!("Kubelet pod refresh failed: {}", e),
+            },
+        }
+    }
+}
+
+/// Reloads the hash policy file when it changes; a refused policy leaves the current one in force
+async fn hash_policy_stage(
+    policy: Arc<HashPolicyEvaluator>,
+    interval: Duration,
+    shutdown: ShutdownSignal,
+) -> Result<(), AgentError> {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        tokio::select! {
+            _ = shutdown.wait() => return Ok(()),
+            _ = ticker.tick() => {}
+        }
+        let policy = policy.clone();
+        let _ = tokio::task::spawn_blocking(move || policy.reload()).await;
+    }
+}
+
+/// Polls Core for approved memory acquisitions and runs them one at a time. Claim failures are
+/// logged and retried at the next poll; shutdown aborts a running acquisition.
+async fn memory_acquisition_stage(acquirer: MemoryAcquirer, shutdown: ShutdownSignal) -> Result<(), AgentError> {
+    let mut ticker = tokio::time::interval(acquirer.poll_interval());
+    loop {
+        tokio::select! {
+            _ = shutdown.wait() => return Ok(()),
+            _ = ticker.tick() => {}
+        }
+        let order = tokio::select! {
+            _ = shutdown.wait() => return Ok(()),
+            res = acquirer.claim() => match res {
+                Ok(Some(order)) => order,
+                Ok(None) => continue,
+                Err(e) => {
+                    warn!("Memory acquisition claim failed: {}", e);
+                    continue;
+                }
+            },
+        };
+        acquirer.run_order(&order, &shutdown).await;
+    }
+}
+
+/// Seals closed log segments into the outbox and ships it every interval, and once more at
+/// shutdown (bounded by SHUTDOWN_DELIVERY_GRACE). Unshipped segments stay in the outbox for the
+/// next run; lines logged after the final seal are only in the local log.
+async fn log_seal_stage(
+    shipper: LogShipper,
+    chain: Arc<LogChain>,
+    signer: Arc<SecurityEventSigner>,
+    shutdown: ShutdownSignal,
+) -> Result<(), AgentError> {
+    let mut ticker = tokio::time::interval(shipper.interval());
+    loop {
+        let stopping = tokio::select! {
+            _ = shutdown.wait() => true,
+            _ = ticker.tick() => false,
+        };
+        if let Err(e) = shipper.seal_pending(&chain, signer.as_ref()) {
+            error!("Log sealing failed: {}", e);
+        }
+        if stopping {
+            let _ = tokio::time::timeout(SHUTDOWN_DELIVERY_GRACE, shipper.ship()).await;
+            return Ok(());
+        }
+        shipper.ship().await;
+    }
+}
+
+impl SegmentSigner for SecurityEventSigner {
+    fn public_key(&self) -> [u8; 32] {
+        self.verifying_key()
+    }
+
+    fn sign_digest(&self, digest: &[u8]) -> Result<String, AgentError> {
+        Ok(self.sign_unsequenced(digest))
+    }
+}
+
+/// Polls Core for rule packs and on-demand scans and runs scheduled scans in between. Matches go
+/// to the signing stage; each scan summary reaches Core with the next agent stats.
+async fn yara_scan_stage(
+    mut scanner: YaraScanner,
+    tx: mpsc::Sender<SignRequest>,
+    health_monitor: Arc<HealthMonitor>,
+    shutdown: ShutdownSignal,
+) -> Result<(), AgentError> {
+    let mut ticker = tokio::time::interval(scanner.poll_interval());
+    loop {
+        tokio::select! {
+            _ = shutdown.wait() => return Ok(()),
+            _ = ticker.tick() => {}
+        }
+        let order = tokio::select! {
+            _ = shutdown.wait() => return Ok(()),
+            res = scanner.poll() => res.unwrap_or_else(|e| {
+                warn!("YARA poll failed: {}", e);
+                None
+            }),
+        };
+        match order {
+            Some(order) => {
+                scanner.scan(ScanKind::OnDemand, Some(order.scan_id), &order.paths, &shutdown, &tx).await;
+            }
+            None if scanner.scheduled_due() => {
+                scanner.scan(ScanKind::Scheduled, None, &[], &shutdown, &tx).await;
+            }
+            None => {}
+        }
+        health_monitor.record_yara(scanner.status());
+    }
+}
+
+struct Supervisor<'a> {
+    hardening: &'a hardening::RuntimeHardening,
+    health_monitor: &'a HealthMonitor,
+    /// Instance id of health reports
+    component_id: &'a str,
+    disk_budget: &'a DiskBudget,
+    delivery: &'a DeliveryManager,
+    log_file: Option<&'a RotatingLogFile>,
+    queue: &'a DeliveryQueue<(String, Vec<u8>)>,
+    backpressure: &'a BackpressureManager,
+    process_monitor: &'a ProcessMonitor,
+    network_monitor: &'a NetworkMonitor,
+    drops: &'a DropLedger,
+    inventory: &'a InventoryCollector,
+    stats_tx: mpsc::Sender<SignRequest>,
+}
+
+/// Runs on the main task until shutdown. Returns an error on a hardening violation; a failed
+/// health check stops the agent cleanly.
+async fn supervise(sup: Supervisor<'_>, shutdown: &ShutdownSignal) -> Result<(), AgentError> {
+    let mut ticker = tokio::time::interval(SUPERVISOR_INTERVAL);
+    let mut last_runtime_check = Instant::now();
+    let mut last_stats = Instant::now();
+    let mut last_inventory: Option<Instant> = None;
     loop {
+        tokio::select! {
+            _ = shutdown.wait() => return Ok(()),
+            _ = ticker.tick() => {}
+        }
+        
         // Record watchdog heartbeat
-        hardening.heartbeat();
+        sup.hardening.heartbeat();
         
-        // Perform periodic runtime checks (every 1000 events)
-        if event_count % 1000 == 0 {
-            if let Err(e) = hardening.perform_runtime_checks() {
+        // Periodic runtime checks
+        if last_runtime_check.elapsed() >= RUNTIME_CHECK_INTERVAL {
+            last_runtime_check = Instant::now();
+            if let Err(e) = sup.hardening.perform_runtime_checks() {
                 error!("Runtime check failed: {}, stopping", e);
-                hardening.stop_watchdog();
                 return Err(AgentError::ConfigurationError(format!("Runtime hardening violation: {}", e)));
             }
             
+            // Disk budget: prune logs / bound spool; exceeded budget is reported as unhealthy
+            sup.health_monitor.record_disk(sup.disk_budget.enforce(sup.delivery.spool(), sup.log_file));
+            
             // Check for tamper detection
-            if hardening.is_tampered() {
+            if sup.hardening.is_tampered() {
                 error!("Tamper detected, stopping immediately");
-                hardening.stop_watchdog();
                 return Err(AgentError::ConfigurationError("Tamper detected - fail-closed".to_string()));
             }
         }
         
         // Check health
-        if !health_monitor.check_health()? {
+        if !sup.health_monitor.check_health()? {
             error!("Health check failed, stopping");
-            hardening.stop_watchdog();
-            break;
-        }
-        
-        // Check backpressure
-        let queue_size = 0; // Would be actual queue size in production
-        backpressure.update_queue_size(queue_size);
-        
-        if backpressure.should_drop(queue_size) {
-            backpressure.signal();
-            continue;
-        }
-        
-        // Check rate limit
-        if !rate_limiter.allow()? {
-            continue;
+            return Ok(());
         }
         
-        // Generate and send events (at least once per second)
-        if event_count % 100 == 0 || event_count == 0 {
-            // Simulate process exec event
-            let process_event = process_monitor.record_exec(
-                (1234 + (event_count % 10000)) as u32,
-                Some(1000),
-                1000,
-                1000,
-                "/usr/bin/test".to_string(),
-                Some("test --arg".to_string()),
-            )?;
-            
-            let features = feature_extractor.extract_from_process(&process_event)?;
-            
-            let envelope_data = serde_json::to_vec(&process_event)
-                .map_err(|e| AgentError::EnvelopeCreationFailed(format!("{}", e)))?;
-            
-            let signature = security_signer.sign(&envelope_data)
-                .map_err(|e| AgentError::SigningFailed(format!("{}", e)))?;
-            
-            let envelope = envelope_builder.build_from_process(&process_event, &features, signature)?;
-            
-            health_monitor.record_event();
-            
-            info!("Event envelope created: {} (sequence: {})", 
-                envelope.event_id, envelope.sequence);
-            
-            // Step 1: Serialize EventEnvelope to canonical JSON bytes
-            let canonical_bytes = serde_json::to_vec(&envelope)
-                .map_err(|e| AgentError::EnvelopeCreationFailed(format!("Failed to serialize envelope: {}", e)))?;
-            
-            // Step 2: SHA-256 hash of canonical bytes
-            use sha2::{Sha256, Digest};
-            let mut hasher = Sha256::new();
-            hasher.update(&canonical_bytes);
-            let hash_bytes = hasher.finalize();
-            let payload_hash = hex::encode(hash_bytes);
-            
-            info!("Signing payload hash={} envelope_id={}", payload_hash, envelope.event_id);
-            
-            // Step 3: Sign the hash using Ed25519 (via SecurityEventSigner)
-            // SecurityEventSigner.sign() includes sequence number, so we sign the hash directly
-            info!("About to sign payload hash (length: {})", hash_bytes.len());
-            let signature = security_signer.sign(&hash_bytes)
-                .map_err(|e| {
-                    error!("Signing failed with error: {}", e);
-                    AgentError::SigningFailed(format!("Failed to sign hash with Ed25519: {}", e))
-                })?;
-            info!("Successfully signed payload hash");
+        if last_stats.elapsed() >= STATS_INTERVAL {
+            last_stats = Instant::now();
+            log_stats(&sup);
             
-            // Step 4: Create SignedEvent with new format
-            use serde_json::json;
-            let signed_event = json!({
-                "envelope": serde_json::from_slice::<serde_json::Value>(&canonical_bytes)
-                    .map_err(|e| AgentError::EnvelopeCreationFailed(format!("Failed to parse envelope JSON: {}", e)))?,
-                "payload_hash": payload_hash,
-                "signature": signature,
-                "signer_id": component_id,
-            });
-            
-            // Send directly via HTTP POST (async call in sync context)
-            let url = format!("{}/ingest/linux", core_api_url);
-            let url_clone = url.clone();
-            let client_clone = http_client.clone();
-            let envelope_id = envelope.event_id.clone();
-            
-            info!("POST /ingest/linux");
-            
-            match rt.block_on(async move {
-                let res = client_clone
-                    .post(&url)
-                    .json(&signed_event)
-                    .send()
-                    .await?;
-                Ok::<_, reqwest::Error>(res)
-            }) {
-                Ok(res) => {
-                    if res.status().is_success() {
-                        info!("POST {} -> {} OK | Telemetry delivered: {}", url_clone, res.status(), envelope_id);
-                    } else {
-                        error!("Failed to send event {}: HTTP {}", envelope_id, res.status());
-                    }
-                }
-                Err(e) => {
-                    error!("Failed to send event {}: {}", envelope_id, e);
-                }
+            // Report per-priority drop counters to Core (lowest priority - shed first under overload)
+            let health_stats = sup.health_monitor.stats();
+            let stats = AgentStatsData {
+                dropped_by_priority: sup.queue.drop_counters(),
+                queue_depth: sup.queue.len(),
+                spool_depth: health_stats.delivery.as_ref().map_or(0, |d| d.spool_depth),
+                circuit_state: health_stats.delivery.as_ref()
+                    .map_or("closed", |d| d.circuit_state.as_str())
+                    .to_string(),
+                kernel_capabilities: health_stats.kernel_capabilities.clone(),
+                run_id: sup.drops.run_id().to_string(),
+                dropped_by_reason: sup.drops.snapshot(sup.delivery.stats().spool_dropped),
+                yara: health_stats.yara.clone(),
+                health: Some(sup.health_monitor.report(sup.component_id)),
+            };
+            // Never block the supervisor on a full signing channel: stats are shed first anyway
+            if sup.stats_tx.try_send(SignRequest::Stats(stats)).is_err() {
+                sup.queue.record_drop(EventPriority::Stats);
+                sup.drops.record(DropReason::ChannelFull);
             }
         }
         
-        event_count += 1;
-        
-        // Periodic stats
-        if event_count % 10000 == 0 {
-            let process_count = process_monitor.process_count();
-            let connection_count = network_monitor.connection_count();
-            let bp_stats = backpressure.stats();
-            let health_stats = health_monitor.stats();
-            
-            info!("Stats: events={}, processes={}, connections={}, dropped={}, healthy={}", 
-                event_count, process_count, connection_count, bp_stats.events_dropped, health_stats.healthy);
+        // Host inventory at startup, then daily; a full signing channel retries on the next tick
+        if last_inventory.is_none_or(|at| at.elapsed() >= INVENTORY_INTERVAL) {
+            let inventory = sup.inventory.collect();
+            if sup.stats_tx.try_send(SignRequest::Inventory(inventory)).is_ok() {
+                last_inventory = Some(Instant::now());
+            }
         }
     }
+}
+
+fn log_stats(sup: &Supervisor<'_>) {
+    let bp_stats = sup.backpressure.stats();
+    let health_stats = sup.health_monitor.stats();
+    let drops = sup.queue.drop_counters();
     
-    syscall_monitor.stop();
-    hardening.stop_watchdog();
-    info!("Linux Agent stopped");
-    Ok(())
+    info!("Stats: processes={}, connections={}, queue={}, dropped={}, healthy={}", 
+        sup.process_monitor.process_count(), sup.network_monitor.connection_count(),
+        sup.queue.len(), bp_stats.events_dropped, health_stats.healthy);
+    info!("Priority drops: critical={}, process_exec={}, telemetry={}, stats={}", 
+        drops.critical, drops.process_exec, drops.telemetry, drops.stats);
+    let by_reason = sup.drops.snapshot(sup.delivery.stats().spool_dropped);
+    info!("Drops by reason (run {}): total={}, rate_limited={}, backpressure={}, queue_full={}, channel_full={}, spool_evicted={}, spool_failed={}", 
+        sup.drops.run_id(), by_reason.total(), by_reason.rate_limited, by_reason.backpressure, by_reason.queue_full,
+        by_reason.channel_full, by_reason.spool_evicted, by_reason.spool_failed);
+    if let Some(d) = &health_stats.delivery {
+        info!("Delivery: circuit={}, consecutive_failures={}, delivered={}, retries={}, retry_budget_exhausted={}, rejected={}, spool_depth={}, spool_dropped={}, bandwidth_wait_ms={}, backlog_deferred={}", 
+            d.circuit_state.as_str(), d.consecutive_failures, d.delivered, d.retries,
+            d.retry_budget_exhausted, d.rejected, d.spool_depth, d.spool_dropped, d.bandwidth_wait_ms, d.backlog_deferred);
+        if !d.rejected_by_category.is_empty() {
+            let by_category: Vec<String> = d.rejected_by_category.iter().map(|(c, n)| format!("{}={}", c, n)).collect();
+            info!("Rejections by validation category: {}", by_category.join(", "));
+        }
+    }
+    if let Some(d) = &health_stats.disk {
+        info!("Disk: spool={} bytes, logs={} bytes, budget={} bytes, exceeded={}", 
+            d.spool_bytes, d.log_bytes, d.budget_bytes, d.exceeded);
+    }
+    if let Some(scan) = health_stats.yara.as_ref().and_then(|y| y.last_scan.as_ref()) {
+        info!("YARA: last {} scan files={}, matches={}, errors={}, completed={}",
+            scan.kind, scan.files_scanned, scan.matches, scan.errors, scan.completed);
+    }
+}
+
+/// Wrap an envelope as a SignedEvent for /ingest/linux
+fn sign_for_delivery(envelope: &EventEnvelope, security_signer: &SecurityEventSigner, sequence: u64, signer_id: &str) -> Result<Vec<u8>, AgentError> {
+    // Step 1: Serialize EventEnvelope to canonical JSON bytes
+    let canonical_bytes = envelope.canonical_bytes()?;
+    
+    // Step 2: SHA-256 hash of canonical bytes
+    let hash_bytes = payload_digest(&canonical_bytes);
+    let payload_hash = hex::encode(hash_bytes);
+    
+    info!("Signing payload hash={} envelope_id={}", payload_hash, envelope.event_id);
+    
+    // Step 3: Sign the hash using Ed25519 (via SecurityEventSigner) under the sequence number
+    // reserved for it, so we sign the hash directly
+    let signature = security_signer.sign_at(sequence, &hash_bytes);
+    
+    // Step 4: Create SignedEvent carrying the canonical bytes verbatim, so ingest can recompute
+    // payload_hash from the envelope it receives; it needs the sequence to rebuild the signed message
+    signed_event_body(&canonical_bytes, &serde_json::json!({
+        "payload_hash": payload_hash,
+        "signature": signature,
+        "signer_id": signer_id,
+        "sequence": sequence,
+    }))
 }
 
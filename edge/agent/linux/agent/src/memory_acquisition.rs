@@ -0,0 +1,419 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_linux_agent/agent/src/memory_acquisition.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Operator-triggered memory acquisition - polls Core for this agent's approved acquisition requests, runs the configured external tool and streams the image to Core in SHA-256-hashed chunks
+
+/*
+ * Memory Acquisition
+ *
+ * Off unless AGENT_MEMORY_ACQUISITION is set, and never started by the agent itself: Core hands
+ * out only acquisitions an operator requested and a second operator approved. Each scope has its
+ * own command template, and a scope without one is refused here whatever Core asks:
+ *
+ *   AGENT_MEMORY_FULL_COMMAND      e.g. "/usr/bin/avml {output}"
+ *   AGENT_MEMORY_PROCESS_COMMAND   e.g. "/usr/local/sbin/ransomeye-procdump {pid} {output}"
+ *
+ * Templates are split on whitespace and run without a shell; {pid} is the integer from the order
+ * and {output} a path the agent chooses, so an order cannot inject arguments. The tool must
+ * write the image to {output}. The image is then read in chunk_bytes pieces and each piece is
+ * uploaded with its SHA-256; completion sends the chunk count, size and SHA-256 of the whole
+ * image plus the tool path and the SHA-256 of the tool binary for the chain of custody. The
+ * local image is removed whatever the outcome; any failure is reported to Core.
+ */
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crypto::digest::Sha256;
+use reqwest::Client as ReqwestClient;
+use serde::Deserialize;
+use tokio::io::AsyncReadExt;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use super::errors::AgentError;
+use super::pipeline::ShutdownSignal;
+
+/// Smallest and largest chunk Core may ask for
+const MIN_CHUNK_BYTES: i64 = 64 * 1024;
+const MAX_CHUNK_BYTES: i64 = 64 * 1024 * 1024;
+/// Attempts per chunk upload (Core accepts a resent chunk)
+const CHUNK_ATTEMPTS: u32 = 3;
+const CHUNK_RETRY_DELAY: Duration = Duration::from_secs(2);
+/// Per-request timeout (one chunk at a time)
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+/// Core refuses longer failure reasons
+const MAX_FAILURE_REASON_CHARS: usize = 512;
+
+/// Acquisition handed out by POST /agents/memory-acquisitions/claim
+#[derive(Debug, Clone, Deserialize)]
+pub struct AcquisitionOrder {
+    pub acquisition_id: Uuid,
+    /// process or full
+    pub scope: String,
+    pub pid: Option<i32>,
+    pub max_bytes: i64,
+    pub chunk_bytes: i64,
+}
+
+/// A command template: absolute program path and arguments with {pid} / {output} placeholders.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AcquisitionCommand {
+    pub program: PathBuf,
+    pub args: Vec<String>,
+}
+
+impl AcquisitionCommand {
+    pub fn parse(template: &str) -> Result<Self, AgentError> {
+        let mut parts = template.split_whitespace();
+        let program = PathBuf::from(parts.next().unwrap_or_default());
+        if !program.is_absolute() {
+            return Err(AgentError::ConfigurationError(format!(
+                "Memory acquisition command '{}' must start with an absolute program path",
+                template
+            )));
+        }
+        let args: Vec<String> = parts.map(str::to_string).collect();
+        if !args.iter().any(|a| a.contains("{output}")) {
+            return Err(AgentError::ConfigurationError(format!(
+                "Memory acquisition command '{}' has no {{output}} argument",
+                template
+            )));
+        }
+        Ok(Self { program, args })
+    }
+
+    /// Arguments for one run; a template using {pid} needs a pid.
+    pub fn render(&self, output: &Path, pid: Option<i32>) -> Result<Vec<String>, AgentError> {
+        self.args
+            .iter()
+            .map(|arg| {
+                let arg = arg.replace("{output}", &output.to_string_lossy());
+                if !arg.contains("{pid}") {
+                    return Ok(arg);
+                }
+                pid.map(|pid| arg.replace("{pid}", &pid.to_string())).ok_or_else(|| {
+                    AgentError::MemoryAcquisitionFailed("command needs a pid the order does not carry".to_string())
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MemoryAcquisitionConfig {
+    pub full_command: Option<AcquisitionCommand>,
+    pub process_command: Option<AcquisitionCommand>,
+    pub work_dir: PathBuf,
+    pub poll_interval: Duration,
+    pub tool_timeout: Duration,
+}
+
+/// The command for an order, if this host allows its scope and the order is within bounds.
+pub fn validate_order<'a>(
+    config: &'a MemoryAcquisitionConfig,
+    order: &AcquisitionOrder,
+) -> Result<&'a AcquisitionCommand, AgentError> {
+    let refuse = |reason: String| Err(AgentError::MemoryAcquisitionFailed(reason));
+    let command = match (order.scope.as_str(), order.pid) {
+        ("full", None) => config.full_command.as_ref(),
+        ("process", Some(pid)) if pid > 0 => config.process_command.as_ref(),
+        (scope, pid) => return refuse(format!("invalid order: scope '{}' with pid {:?}", scope, pid)),
+    };
+    let Some(command) = command else {
+        return refuse(format!("{} memory acquisition is not enabled on this host", order.scope));
+    };
+    if !(MIN_CHUNK_BYTES..=MAX_CHUNK_BYTES).contains(&order.chunk_bytes) {
+        return refuse(format!("chunk size {} out of bounds", order.chunk_bytes));
+    }
+    if order.max_bytes < 1 {
+        return refuse(format!("image bound {} out of bounds", order.max_bytes));
+    }
+    Ok(command)
+}
+
+/// Fill `buf` from `file` unless EOF comes first; returns the bytes read.
+async fn read_chunk(file: &mut tokio::fs::File, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        let n = file.read(&mut buf[filled..]).await?;
+        if n == 0 {
+            break;
+        }
+        filled += n;
+    }
+    Ok(filled)
+}
+
+fn failed(context: &str) -> impl FnOnce(reqwest::Error) -> AgentError + '_ {
+    move |e| AgentError::MemoryAcquisitionFailed(format!("{}: {}", context, e))
+}
+
+/// Memory acquisition worker (one acquisition at a time)
+pub struct MemoryAcquirer {
+    client: ReqwestClient,
+    core_api_url: String,
+    api_token: String,
+    config: MemoryAcquisitionConfig,
+}
+
+impl MemoryAcquirer {
+    pub fn new(core_api_url: String, api_token: String, config: MemoryAcquisitionConfig) -> Result<Self, AgentError> {
+        std::fs::create_dir_all(&config.work_dir).map_err(|e| {
+            AgentError::ConfigurationError(format!(
+                "Failed to create memory work directory {}: {}",
+                config.work_dir.display(),
+                e
+            ))
+        })?;
+        let client = ReqwestClient::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| AgentError::ConfigurationError(format!("Failed to create HTTP client: {}", e)))?;
+        Ok(Self { client, core_api_url, api_token, config })
+    }
+
+    pub fn poll_interval(&self) -> Duration {
+        self.config.poll_interval
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.core_api_url, path)
+    }
+
+    /// This agent's pending acquisition, if any
+    pub async fn claim(&self) -> Result<Option<AcquisitionOrder>, AgentError> {
+        let res = self
+            .client
+            .post(self.url("/agents/memory-acquisitions/claim"))
+            .bearer_auth(&self.api_token)
+            .send()
+            .await
+            .map_err(failed("claim"))?;
+        match res.status() {
+            reqwest::StatusCode::NO_CONTENT => Ok(None),
+            s if s.is_success() => res.json::<AcquisitionOrder>().await.map(Some).map_err(failed("claim: invalid order")),
+            s => Err(AgentError::MemoryAcquisitionFailed(format!("claim: HTTP {}", s))),
+        }
+    }
+
+    /// Run a claimed acquisition to completion, or report why it failed. Shutdown aborts it
+    /// (the tool is killed) and is reported as a failure.
+    pub async fn run_order(&self, order: &AcquisitionOrder, shutdown: &ShutdownSignal) {
+        let output = self.config.work_dir.join(format!("{}.mem", order.acquisition_id));
+        let result = tokio::select! {
+            _ = shutdown.wait() => Err(AgentError::MemoryAcquisitionFailed("agent shutting down".to_string())),
+            res = self.acquire_and_stream(order, &output) => res,
+        };
+        let _ = tokio::fs::remove_file(&output).await;
+        if let Err(e) = result {
+            error!("Memory acquisition {} failed: {}", order.acquisition_id, e);
+            self.report_failure(order, &e.to_string()).await;
+        }
+    }
+
+    async fn acquire_and_stream(&self, order: &AcquisitionOrder, output: &Path) -> Result<(), AgentError> {
+        let command = validate_order(&self.config, order)?;
+        let args = command.render(output, order.pid)?;
+        info!(
+            "Memory acquisition {} | scope={} | pid={:?} | tool={}",
+            order.acquisition_id, order.scope, order.pid, command.program.display()
+        );
+        let tool_sha256 = {
+            let program = command.program.clone();
+            tokio::task::spawn_blocking(move || std::fs::read(&program).map(|b| hex::encode(Sha256::digest(&b))))
+                .await
+                .map_err(|e| AgentError::MemoryAcquisitionFailed(format!("tool hash task: {}", e)))?
+                .map_err(|e| AgentError::MemoryAcquisitionFailed(format!("tool {} not readable: {}", command.program.display(), e)))?
+        };
+
+        let mut child = tokio::process::Command::new(&command.program)
+            .args(&args)
+            .stdin(std::process::Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| AgentError::MemoryAcquisitionFailed(format!("failed to start {}: {}", command.program.display(), e)))?;
+        let status = tokio::time::timeout(self.config.tool_timeout, child.wait())
+            .await
+            .map_err(|_| AgentError::MemoryAcquisitionFailed(format!("tool timed out after {:?}", self.config.tool_timeout)))?
+            .map_err(|e| AgentError::MemoryAcquisitionFailed(format!("tool: {}", e)))?;
+        if !status.success() {
+            return Err(AgentError::MemoryAcquisitionFailed(format!("tool exited with {}", status)));
+        }
+
+        let size = tokio::fs::metadata(output)
+            .await
+            .map_err(|e| AgentError::MemoryAcquisitionFailed(format!("tool wrote no image at {}: {}", output.display(), e)))?
+            .len();
+        if size == 0 || size > order.max_bytes as u64 {
+            return Err(AgentError::MemoryAcquisitionFailed(format!(
+                "image of {} bytes outside 1..={}",
+                size, order.max_bytes
+            )));
+        }
+
+        let (chunks, bytes, image_sha256) = self.stream(order, output).await?;
+        let res = self
+            .client
+            .post(self.url("/agents/memory-acquisitions/complete"))
+            .bearer_auth(&self.api_token)
+            .json(&serde_json::json!({
+                "acquisition_id": order.acquisition_id,
+                "chunk_count": chunks,
+                "image_bytes": bytes,
+                "image_sha256": image_sha256,
+                "tool": command.program.to_string_lossy(),
+                "tool_version": format!("sha256:{}", tool_sha256),
+            }))
+            .send()
+            .await
+            .map_err(failed("complete"))?;
+        if !res.status().is_success() {
+            return Err(AgentError::MemoryAcquisitionFailed(format!("complete: HTTP {}", res.status())));
+        }
+        info!(
+            "Memory acquisition {} streamed | chunks={} | bytes={} | sha256={}",
+            order.acquisition_id, chunks, bytes, image_sha256
+        );
+        Ok(())
+    }
+
+    /// Upload the image chunk by chunk; returns chunk count, size and SHA-256 of the whole image
+    async fn stream(&self, order: &AcquisitionOrder, output: &Path) -> Result<(u32, u64, String), AgentError> {
+        let mut file = tokio::fs::File::open(output)
+            .await
+            .map_err(|e| AgentError::MemoryAcquisitionFailed(format!("image not readable: {}", e)))?;
+        let mut buf = vec![0u8; order.chunk_bytes as usize];
+        let mut image = Sha256::new();
+        let mut index = 0u32;
+        let mut total = 0u64;
+        loop {
+            let n = read_chunk(&mut file, &mut buf)
+                .await
+                .map_err(|e| AgentError::MemoryAcquisitionFailed(format!("image read: {}", e)))?;
+            if n == 0 {
+                break;
+            }
+            let chunk = &buf[..n];
+            image.update(chunk);
+            self.upload_chunk(order.acquisition_id, index, chunk).await?;
+            index += 1;
+            total += n as u64;
+        }
+        Ok((index, total, hex::encode(image.finalize())))
+    }
+
+    async fn upload_chunk(&self, acquisition_id: Uuid, index: u32, chunk: &[u8]) -> Result<(), AgentError> {
+        let sha256 = hex::encode(Sha256::digest(chunk));
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let sent = self
+                .client
+                .post(self.url("/agents/memory-acquisitions/chunk"))
+                .bearer_auth(&self.api_token)
+                .header("X-Acquisition-Id", acquisition_id.to_string())
+                .header("X-Chunk-Index", index.to_string())
+                .header("X-Content-SHA256", &sha256)
+                .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+                .body(chunk.to_vec())
+                .send()
+                .await;
+            let error = match sent {
+                Ok(res) if res.status().is_success() => return Ok(()),
+                // Refusals are final; only transport errors and server errors are retried
+                Ok(res) if res.status().is_client_error() => {
+                    return Err(AgentError::MemoryAcquisitionFailed(format!("chunk {}: HTTP {}", index, res.status())));
+                }
+                Ok(res) => format!("HTTP {}", res.status()),
+                Err(e) => e.to_string(),
+            };
+            if attempt >= CHUNK_ATTEMPTS {
+                return Err(AgentError::MemoryAcquisitionFailed(format!("chunk {}: {}", index, error)));
+            }
+            warn!("Memory chunk {}#{} upload failed ({}), retrying", acquisition_id, index, error);
+            tokio::time::sleep(CHUNK_RETRY_DELAY).await;
+        }
+    }
+
+    async fn report_failure(&self, order: &AcquisitionOrder, reason: &str) {
+        let reason: String = reason.chars().take(MAX_FAILURE_REASON_CHARS).collect();
+        let sent = self
+            .client
+            .post(self.url("/agents/memory-acquisitions/fail"))
+            .bearer_auth(&self.api_token)
+            .json(&serde_json::json!({"acquisition_id": order.acquisition_id, "reason": reason}))
+            .send()
+            .await;
+        match sent {
+            Ok(res) if res.status().is_success() => {}
+            Ok(res) => warn!("Failed to report memory acquisition {} failure: HTTP {}", order.acquisition_id, res.status()),
+            Err(e) => warn!("Failed to report memory acquisition {} failure: {}", order.acquisition_id, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> MemoryAcquisitionConfig {
+        MemoryAcquisitionConfig {
+            full_command: Some(AcquisitionCommand::parse("/usr/bin/avml {output}").unwrap()),
+            process_command: None,
+            work_dir: PathBuf::from("/tmp"),
+            poll_interval: Duration::from_secs(30),
+            tool_timeout: Duration::from_secs(60),
+        }
+    }
+
+    fn order(scope: &str, pid: Option<i32>) -> AcquisitionOrder {
+        AcquisitionOrder { acquisition_id: Uuid::new_v4(), scope: scope.to_string(), pid, max_bytes: 1 << 30, chunk_bytes: 1 << 20 }
+    }
+
+    #[test]
+    fn test_command_templates() {
+        assert!(AcquisitionCommand::parse("avml {output}").is_err(), "relative program");
+        assert!(AcquisitionCommand::parse("/usr/bin/avml").is_err(), "no output argument");
+
+        let cmd = AcquisitionCommand::parse("/usr/local/sbin/procdump  -p {pid}  -o {output}").unwrap();
+        let args = cmd.render(Path::new("/var/lib/mem/a.mem"), Some(4242)).unwrap();
+        assert_eq!(args, vec!["-p", "4242", "-o", "/var/lib/mem/a.mem"]);
+        assert!(cmd.render(Path::new("/var/lib/mem/a.mem"), None).is_err());
+
+        // A value from the order never becomes more than one argument
+        let full = AcquisitionCommand::parse("/usr/bin/avml {output}").unwrap();
+        assert_eq!(full.render(Path::new("/w/x y.mem"), None).unwrap(), vec!["/w/x y.mem"]);
+    }
+
+    #[test]
+    fn test_orders_are_checked_against_local_scopes() {
+        let config = config();
+        assert!(validate_order(&config, &order("full", None)).is_ok());
+        // Process scope has no command on this host
+        assert!(validate_order(&config, &order("process", Some(4242))).is_err());
+        assert!(validate_order(&config, &order("full", Some(1))).is_err());
+        assert!(validate_order(&config, &order("kernel", None)).is_err());
+
+        let mut big = order("full", None);
+        big.chunk_bytes = MAX_CHUNK_BYTES + 1;
+        assert!(validate_order(&config, &big).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_chunk_fills_until_eof() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("image.mem");
+        std::fs::write(&path, vec![7u8; 10]).unwrap();
+        let mut file = tokio::fs::File::open(&path).await.unwrap();
+        let mut buf = vec![0u8; 4];
+        let mut sizes = Vec::new();
+        loop {
+            let n = read_chunk(&mut file, &mut buf).await.unwrap();
+            if n == 0 {
+                break;
+            }
+            sizes.push(n);
+        }
+        assert_eq!(sizes, vec![4, 4, 2]);
+    }
+}
@@ -1,7 +1,20 @@
 // Path and File Name : /home/ransomeye/rebuild/ransomeye_linux_agent/agent/src/network.rs
 // Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
-// Details of functionality of this file: Network monitoring - socket operations (light)
+// Details of functionality of this file: Network monitoring - socket operations (light), attributed to the network namespace of the owning process
 
+/*
+ * Network Namespace Attribution
+ *
+ * Each socket is attributed to the network namespace of the process that created it, read from
+ * the /proc/<pid>/ns/net link ("net:[<inode>]"). The namespace is captured at socket create and
+ * kept with the tracked connection, so a later connect is attributed correctly even if the
+ * process has moved namespaces (setns) or exited. host_netns compares against the namespace of
+ * pid 1: false means the addresses are those of a container/pod network, true includes
+ * containers running with host networking. Container id and pod come from the container
+ * context attached to the envelope (container.rs).
+ */
+
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -35,6 +48,10 @@ pub struct NetworkEvent {
     pub local_addr: Option<String>,
     pub local_port: Option<u16>,
     pub bytes_transferred: Option<u64>,
+    /// Network namespace inode of the socket (None if /proc could not be read)
+    pub netns_inode: Option<u64>,
+    /// Whether that namespace is the host's (None if either is unknown)
+    pub host_netns: Option<bool>,
     pub timestamp: u64,
 }
 
@@ -46,6 +63,8 @@ pub struct NetworkMonitor {
     connections: Arc<parking_lot::RwLock<std::collections::HashMap<u64, ConnectionInfo>>>,
     max_connections: usize,
     events_processed: Arc<AtomicU64>,
+    host_root: PathBuf,
+    host_netns: Option<u64>,
 }
 
 #[derive(Debug, Clone)]
@@ -58,6 +77,7 @@ struct ConnectionInfo {
     remote_port: Option<u16>,
     local_addr: Option<String>,
     local_port: Option<u16>,
+    netns_inode: Option<u64>,
     first_seen: u64,
     last_seen: u64,
 }
@@ -65,13 +85,31 @@ struct ConnectionInfo {
 impl NetworkMonitor {
     /// Create new network monitor
     pub fn new(max_connections: usize) -> Self {
+        Self::with_host_root(max_connections, "/")
+    }
+    
+    /// Network monitor reading /proc below `host_root` (AGENT_HOST_ROOT)
+    pub fn with_host_root(max_connections: usize, host_root: impl AsRef<Path>) -> Self {
+        let host_root = host_root.as_ref().to_path_buf();
+        let host_netns = read_netns(&host_root, 1);
         Self {
             connections: Arc::new(parking_lot::RwLock::new(std::collections::HashMap::new())),
             max_connections,
             events_processed: Arc::new(AtomicU64::new(0)),
+            host_root,
+            host_netns,
         }
     }
     
+    /// Network namespace inode of `pid`
+    pub fn netns_of(&self, pid: u32) -> Option<u64> {
+        read_netns(&self.host_root, pid)
+    }
+    
+    fn is_host_netns(&self, netns_inode: Option<u64>) -> Option<bool> {
+        Some(netns_inode? == self.host_netns?)
+    }
+    
     /// Record socket create event
     pub fn record_socket_create(&self, pid: u32, uid: u32, gid: u32, 
                                family: u32, socket_type: u32, socket_fd: i32) -> Result<NetworkEvent, AgentError> {
@@ -80,6 +118,8 @@ impl NetworkMonitor {
             .map_err(|e| AgentError::NetworkMonitoringFailed(format!("Time error: {}", e)))?
             .as_secs();
         
+        let netns_inode = self.netns_of(pid);
+        
         // Track connection
         {
             let mut connections = self.connections.write();
@@ -98,6 +138,7 @@ impl NetworkMonitor {
                 remote_port: None,
                 local_addr: None,
                 local_port: None,
+                netns_inode,
                 first_seen: timestamp,
                 last_seen: timestamp,
             });
@@ -119,6 +160,8 @@ impl NetworkMonitor {
             local_addr: None,
             local_port: None,
             bytes_transferred: None,
+            netns_inode,
+            host_netns: self.is_host_netns(netns_inode),
             timestamp,
         })
     }
@@ -131,16 +174,18 @@ impl NetworkMonitor {
             .map_err(|e| AgentError::NetworkMonitoringFailed(format!("Time error: {}", e)))?
             .as_secs();
         
-        // Update connection info
-        {
+        // Update connection info; the namespace is the one the socket was created in
+        let tracked_netns = {
             let mut connections = self.connections.write();
             let conn_id = (pid as u64) << 32 | (socket_fd as u32 as u64);
-            if let Some(conn) = connections.get_mut(&conn_id) {
+            connections.get_mut(&conn_id).and_then(|conn| {
                 conn.remote_addr = Some(remote_addr.clone());
                 conn.remote_port = Some(remote_port);
                 conn.last_seen = timestamp;
-            }
-        }
+                conn.netns_inode
+            })
+        };
+        let netns_inode = tracked_netns.or_else(|| self.netns_of(pid));
         
         self.events_processed.fetch_add(1, Ordering::Relaxed);
         
@@ -158,6 +203,8 @@ impl NetworkMonitor {
             local_addr: None,
             local_port: None,
             bytes_transferred: None,
+            netns_inode,
+            host_netns: self.is_host_netns(netns_inode),
             timestamp,
         })
     }
@@ -193,3 +240,57 @@ impl NetworkMonitor {
     }
 }
 
+/// Inode from a /proc/<pid>/ns/net link target ("net:[4026531840]")
+pub fn parse_netns_link(target: &str) -> Option<u64> {
+    target.strip_prefix("net:[")?.strip_suffix(']')?.parse().ok()
+}
+
+fn read_netns(host_root: &Path, pid: u32) -> Option<u64> {
+    let target = std::fs::read_link(host_root.join(format!("proc/{}/ns/net", pid))).ok()?;
+    parse_netns_link(target.to_str()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn fake_proc(root: &Path, pid: u32, inode: u64) {
+        let ns = root.join(format!("proc/{}/ns", pid));
+        std::fs::create_dir_all(&ns).unwrap();
+        std::os::unix::fs::symlink(format!("net:[{}]", inode), ns.join("net")).unwrap();
+    }
+
+    #[test]
+    fn test_parse_netns_link() {
+        assert_eq!(parse_netns_link("net:[4026531840]"), Some(4026531840));
+        assert_eq!(parse_netns_link("mnt:[4026531840]"), None);
+        assert_eq!(parse_netns_link("net:[]"), None);
+    }
+
+    #[test]
+    fn test_connect_keeps_namespace_of_socket_create() {
+        let root = TempDir::new().unwrap();
+        fake_proc(root.path(), 1, 4026531840);
+        fake_proc(root.path(), 200, 4026532500);
+        let monitor = NetworkMonitor::with_host_root(100, root.path());
+
+        let created = monitor.record_socket_create(200, 0, 0, 2, 1, 7).unwrap();
+        assert_eq!(created.netns_inode, Some(4026532500));
+        assert_eq!(created.host_netns, Some(false));
+
+        // Namespace changes after the socket was created (setns) do not move the socket
+        std::fs::remove_file(root.path().join("proc/200/ns/net")).unwrap();
+        std::os::unix::fs::symlink("net:[4026531840]", root.path().join("proc/200/ns/net")).unwrap();
+        let connected = monitor.record_socket_connect(200, 0, 0, 7, "10.0.0.5".to_string(), 443).unwrap();
+        assert_eq!(connected.netns_inode, Some(4026532500));
+        assert_eq!(connected.host_netns, Some(false));
+
+        // Untracked socket: namespace read at connect time
+        let untracked = monitor.record_socket_connect(200, 0, 0, 9, "10.0.0.5".to_string(), 443).unwrap();
+        assert_eq!(untracked.host_netns, Some(true));
+
+        let unknown = monitor.record_socket_connect(300, 0, 0, 3, "10.0.0.5".to_string(), 443).unwrap();
+        assert_eq!((unknown.netns_inode, unknown.host_netns), (None, None));
+    }
+}
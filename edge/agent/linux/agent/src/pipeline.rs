@@ -0,0 +1,233 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_linux_agent/agent/src/pipeline.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Async pipeline plumbing - shutdown signal, priority delivery queue and stage supervision for the agent's task-per-stage main loop
+
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{watch, Notify};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info};
+
+use super::errors::AgentError;
+use super::priority::{DropCounters, EventPriority, PriorityEventQueue, PushOutcome};
+
+/// Agent-wide shutdown. Triggered once (signal, fatal stage error, failed health check);
+/// stages stop producing and the channels between them close front to back.
+#[derive(Clone)]
+pub struct ShutdownSignal {
+    triggered_at: Arc<watch::Sender<Option<Instant>>>,
+}
+
+impl ShutdownSignal {
+    pub fn new() -> Self {
+        Self { triggered_at: Arc::new(watch::channel(None).0) }
+    }
+
+    pub fn trigger(&self) {
+        self.triggered_at.send_if_modified(|at| {
+            if at.is_some() {
+                return false;
+            }
+            *at = Some(Instant::now());
+            true
+        });
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        self.triggered_at.borrow().is_some()
+    }
+
+    /// Resolves once shutdown has been triggered (immediately if it already was)
+    pub async fn wait(&self) {
+        let mut rx = self.triggered_at.subscribe();
+        let _ = rx.wait_for(|at| at.is_some()).await;
+    }
+
+    /// Whether shutdown was triggered more than `grace` ago
+    pub fn grace_expired(&self, grace: Duration) -> bool {
+        self.triggered_at.borrow().is_some_and(|at| at.elapsed() >= grace)
+    }
+}
+
+impl Default for ShutdownSignal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Resolves on SIGTERM (systemd stop) or SIGINT
+pub async fn wait_for_termination() -> Result<&'static str, AgentError> {
+    let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+        .map_err(|e| AgentError::PipelineFailed(format!("Failed to install SIGTERM handler: {}", e)))?;
+    tokio::select! {
+        _ = sigterm.recv() => Ok("SIGTERM"),
+        res = tokio::signal::ctrl_c() => res
+            .map(|_| "SIGINT")
+            .map_err(|e| AgentError::PipelineFailed(format!("Failed to listen for SIGINT: {}", e))),
+    }
+}
+
+/// Priority queue between the signing and delivery stages. Producers push without blocking
+/// (overload is handled by eviction, not by waiting); the consumer awaits the next event.
+pub struct DeliveryQueue<T> {
+    queue: PriorityEventQueue<T>,
+    ready: Notify,
+    closed: AtomicBool,
+}
+
+impl<T> DeliveryQueue<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            queue: PriorityEventQueue::new(capacity),
+            ready: Notify::new(),
+            closed: AtomicBool::new(false),
+        }
+    }
+
+    pub fn push(&self, priority: EventPriority, item: T) -> PushOutcome {
+        let outcome = self.queue.push(priority, item);
+        if outcome != PushOutcome::Dropped {
+            self.ready.notify_one();
+        }
+        outcome
+    }
+
+    /// No more pushes; `next` returns None once the queue is empty
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+        self.ready.notify_one();
+    }
+
+    /// Highest-priority event, waiting while the queue is empty and open
+    pub async fn next(&self) -> Option<(EventPriority, T)> {
+        loop {
+            if let Some(item) = self.queue.pop() {
+                return Some(item);
+            }
+            if self.closed.load(Ordering::Acquire) {
+                // A push may have raced the close
+                return self.queue.pop();
+            }
+            self.ready.notified().await;
+        }
+    }
+
+    pub fn record_drop(&self, priority: EventPriority) {
+        self.queue.record_drop(priority);
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    pub fn drop_counters(&self) -> DropCounters {
+        self.queue.drop_counters()
+    }
+}
+
+/// Run one pipeline stage as a task. A failing stage triggers shutdown so the rest drain.
+pub fn spawn_stage<F>(name: &'static str, shutdown: &ShutdownSignal, stage: F) -> JoinHandle<Result<(), AgentError>>
+where
+    F: Future<Output = Result<(), AgentError>> + Send + 'static,
+{
+    let shutdown = shutdown.clone();
+    tokio::spawn(async move {
+        let result = stage.await;
+        match &result {
+            Ok(()) => debug!("Pipeline stage {} stopped", name),
+            Err(e) => {
+                error!("Pipeline stage {} failed: {}, shutting down", name, e);
+                shutdown.trigger();
+            }
+        }
+        result
+    })
+}
+
+/// Wait for every stage; the first error (or panic) wins
+pub async fn join_stages(stages: Vec<(&'static str, JoinHandle<Result<(), AgentError>>)>) -> Result<(), AgentError> {
+    let mut first_error = None;
+    for (name, handle) in stages {
+        let result = handle
+            .await
+            .unwrap_or_else(|e| Err(AgentError::PipelineFailed(format!("stage {} panicked: {}", name, e))));
+        if let Err(e) = result {
+            first_error.get_or_insert(e);
+        }
+    }
+    match first_error {
+        Some(e) => Err(e),
+        None => {
+            info!("All pipeline stages stopped");
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_delivery_queue_drains_by_priority_then_ends_after_close() {
+        let queue = Arc::new(DeliveryQueue::new(10));
+        let consumer = {
+            let queue = queue.clone();
+            tokio::spawn(async move {
+                let mut seen = Vec::new();
+                while let Some((_, item)) = queue.next().await {
+                    seen.push(item);
+                }
+                seen
+            })
+        };
+
+        queue.push(EventPriority::Stats, "stats");
+        queue.push(EventPriority::Critical, "canary");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        queue.push(EventPriority::ProcessExec, "exec");
+        queue.close();
+
+        let seen = tokio::time::timeout(Duration::from_secs(1), consumer).await.unwrap().unwrap();
+        assert_eq!(seen, vec!["canary", "stats", "exec"]);
+        assert!(queue.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_wakes_waiters_once() {
+        let shutdown = ShutdownSignal::new();
+        let waiter = {
+            let shutdown = shutdown.clone();
+            tokio::spawn(async move { shutdown.wait().await })
+        };
+        assert!(!shutdown.is_triggered());
+        assert!(!shutdown.grace_expired(Duration::ZERO));
+
+        shutdown.trigger();
+        shutdown.trigger();
+        tokio::time::timeout(Duration::from_secs(1), waiter).await.unwrap().unwrap();
+        assert!(shutdown.is_triggered());
+        assert!(shutdown.grace_expired(Duration::ZERO));
+        assert!(!shutdown.grace_expired(Duration::from_secs(60)));
+        // Already triggered: returns immediately
+        shutdown.wait().await;
+    }
+
+    #[tokio::test]
+    async fn test_failed_stage_triggers_shutdown() {
+        let shutdown = ShutdownSignal::new();
+        let ok = spawn_stage("ok", &shutdown, async { Ok(()) });
+        let failing = spawn_stage("failing", &shutdown, async {
+            Err(AgentError::SigningFailed("key unavailable".to_string()))
+        });
+        let result = join_stages(vec![("ok", ok), ("failing", failing)]).await;
+        assert!(matches!(result, Err(AgentError::SigningFailed(_))));
+        assert!(shutdown.is_triggered());
+    }
+}
@@ -0,0 +1,263 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_linux_agent/agent/src/priority.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Priority-aware bounded event queue - under overload the lowest-priority events are dropped first
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use super::envelope::EventEnvelope;
+
+/// Event priority (lower value = more important)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EventPriority {
+    /// Detections (mass write), canary triggers, YARA matches and blocklisted execs - never shed while anything else can be
+    Critical = 0,
+    /// Process exec
+    ProcessExec = 1,
+    /// Other process / filesystem / network telemetry, and allowlisted execs
+    Telemetry = 2,
+    /// Periodic agent statistics
+    Stats = 3,
+}
+
+pub const PRIORITY_LEVELS: usize = 4;
+
+impl EventPriority {
+    pub const ALL: [EventPriority; PRIORITY_LEVELS] = [
+        EventPriority::Critical,
+        EventPriority::ProcessExec,
+        EventPriority::Telemetry,
+        EventPriority::Stats,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventPriority::Critical => "critical",
+            EventPriority::ProcessExec => "process_exec",
+            EventPriority::Telemetry => "telemetry",
+            EventPriority::Stats => "stats",
+        }
+    }
+
+    /// Classify an envelope by what it carries
+    pub fn classify(envelope: &EventEnvelope) -> Self {
+        let data = &envelope.data;
+        match data.event_category.as_str() {
+            "canary" | "detection" | "yara_match" => return EventPriority::Critical,
+            "agent_stats" => return EventPriority::Stats,
+            _ => {}
+        }
+        if data.filesystem_data.as_ref().map_or(false, |f| f.event_type == "MassWrite") {
+            return EventPriority::Critical;
+        }
+        if let Some(process) = data.process_data.as_ref().filter(|p| p.event_type == "Exec") {
+            return match process.hash_policy.as_ref().map(|h| h.verdict.as_str()) {
+                Some("blocked") => EventPriority::Critical,
+                Some("allowed") => EventPriority::Telemetry,
+                _ => EventPriority::ProcessExec,
+            };
+        }
+        EventPriority::Telemetry
+    }
+}
+
+/// Per-priority drop counters (cumulative since agent start)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DropCounters {
+    pub critical: u64,
+    pub process_exec: u64,
+    pub telemetry: u64,
+    pub stats: u64,
+}
+
+impl DropCounters {
+    pub fn total(&self) -> u64 {
+        self.critical + self.process_exec + self.telemetry + self.stats
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushOutcome {
+    Queued,
+    /// Queued after evicting the oldest event of the given (lower) priority
+    Evicted(EventPriority),
+    /// Incoming event dropped - nothing lower-priority to evict
+    Dropped,
+}
+
+/// Priority event queue
+///
+/// Bounded across all priorities. When full, the oldest event of the lowest priority that is
+/// strictly below the incoming one is evicted; if there is none, the incoming event is dropped.
+/// Dequeue is highest-priority first, FIFO within a priority.
+pub struct PriorityEventQueue<T> {
+    capacity: usize,
+    queues: Mutex<[VecDeque<T>; PRIORITY_LEVELS]>,
+    dropped: [AtomicU64; PRIORITY_LEVELS],
+}
+
+impl<T> PriorityEventQueue<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            queues: Mutex::new(Default::default()),
+            dropped: Default::default(),
+        }
+    }
+
+    pub fn push(&self, priority: EventPriority, item: T) -> PushOutcome {
+        let mut queues = self.queues.lock();
+        let len: usize = queues.iter().map(|q| q.len()).sum();
+
+        let mut outcome = PushOutcome::Queued;
+        if len >= self.capacity {
+            let victim = EventPriority::ALL.iter().rev()
+                .take_while(|p| **p > priority)
+                .find(|p| !queues[**p as usize].is_empty())
+                .copied();
+            match victim {
+                Some(victim) => {
+                    queues[victim as usize].pop_front();
+                    self.record_drop(victim);
+                    outcome = PushOutcome::Evicted(victim);
+                }
+                None => {
+                    self.record_drop(priority);
+                    if priority == EventPriority::Critical {
+                        warn!("Priority queue full of critical events: dropping critical event");
+                    }
+                    return PushOutcome::Dropped;
+                }
+            }
+        }
+
+        queues[priority as usize].push_back(item);
+        outcome
+    }
+
+    /// Highest-priority event, oldest first
+    pub fn pop(&self) -> Option<(EventPriority, T)> {
+        let mut queues = self.queues.lock();
+        EventPriority::ALL.iter()
+            .find_map(|p| queues[*p as usize].pop_front().map(|item| (*p, item)))
+    }
+
+    /// Count an event shed before it reached the queue (e.g. backpressure admission)
+    pub fn record_drop(&self, priority: EventPriority) {
+        self.dropped[priority as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn len(&self) -> usize {
+        self.queues.lock().iter().map(|q| q.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn drop_counters(&self) -> DropCounters {
+        let get = |p: EventPriority| self.dropped[p as usize].load(Ordering::Relaxed);
+        DropCounters {
+            critical: get(EventPriority::Critical),
+            process_exec: get(EventPriority::ProcessExec),
+            telemetry: get(EventPriority::Telemetry),
+            stats: get(EventPriority::Stats),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::envelope::{EnvelopeBuilder, ProcessData};
+    use crate::hash_policy::HashPolicyMatch;
+    use crate::yara_scan::YaraMatchData;
+
+    #[test]
+    fn test_pop_order_is_priority_then_fifo() {
+        let queue = PriorityEventQueue::new(10);
+        queue.push(EventPriority::Stats, "stats");
+        queue.push(EventPriority::ProcessExec, "exec-1");
+        queue.push(EventPriority::Critical, "canary");
+        queue.push(EventPriority::ProcessExec, "exec-2");
+
+        let order: Vec<_> = std::iter::from_fn(|| queue.pop().map(|(_, item)| item)).collect();
+        assert_eq!(order, vec!["canary", "exec-1", "exec-2", "stats"]);
+    }
+
+    #[test]
+    fn test_full_queue_evicts_lowest_priority_first() {
+        let queue = PriorityEventQueue::new(3);
+        queue.push(EventPriority::Stats, 1);
+        queue.push(EventPriority::Telemetry, 2);
+        queue.push(EventPriority::ProcessExec, 3);
+
+        assert_eq!(queue.push(EventPriority::Critical, 4), PushOutcome::Evicted(EventPriority::Stats));
+        assert_eq!(queue.push(EventPriority::ProcessExec, 5), PushOutcome::Evicted(EventPriority::Telemetry));
+        // Only equal-or-higher priority left: incoming low-priority event is dropped
+        assert_eq!(queue.push(EventPriority::Stats, 6), PushOutcome::Dropped);
+        assert_eq!(queue.push(EventPriority::ProcessExec, 7), PushOutcome::Dropped);
+
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.drop_counters(), DropCounters { critical: 0, process_exec: 1, telemetry: 1, stats: 2 });
+    }
+
+    fn yara_hit() -> YaraMatchData {
+        YaraMatchData {
+            rule: "LockBit_Note".to_string(),
+            meta: Default::default(),
+            path: "/home/a/Restore-My-Files.txt".to_string(),
+            file_sha256: None,
+            file_size: None,
+            pack_name: "ransomware-families".to_string(),
+            pack_version: 7,
+            scan_kind: "scheduled".to_string(),
+            scan_id: None,
+            severity: "high".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_yara_matches_are_critical() {
+        let envelope = EnvelopeBuilder::new("linux_agent".to_string(), "agent-1".to_string())
+            .build_yara_match(1, yara_hit(), String::new())
+            .unwrap();
+        assert_eq!(EventPriority::classify(&envelope), EventPriority::Critical);
+    }
+
+    #[test]
+    fn test_hash_policy_verdict_moves_exec_priority() {
+        let mut envelope = EnvelopeBuilder::new("linux_agent".to_string(), "agent-1".to_string())
+            .build_yara_match(1, yara_hit(), String::new())
+            .unwrap();
+        envelope.data.event_category = "process".to_string();
+        envelope.data.yara_match = None;
+        let exec = |verdict: Option<&str>| ProcessData {
+            event_type: "Exec".to_string(),
+            ppid: None,
+            executable: Some("/tmp/dropper".to_string()),
+            command_line: None,
+            mmap_address: None,
+            mmap_size: None,
+            lineage: None,
+            sha256: Some("ab".repeat(32)),
+            hash_policy: verdict.map(|v| HashPolicyMatch {
+                verdict: v.to_string(),
+                severity: if v == "blocked" { "critical" } else { "info" }.to_string(),
+                policy_name: "fleet".to_string(),
+                policy_version: 1,
+                note: None,
+                enforcement: None,
+            }),
+        };
+        envelope.data.process_data = Some(exec(None));
+        assert_eq!(EventPriority::classify(&envelope), EventPriority::ProcessExec);
+        envelope.data.process_data = Some(exec(Some("blocked")));
+        assert_eq!(EventPriority::classify(&envelope), EventPriority::Critical);
+        envelope.data.process_data = Some(exec(Some("allowed")));
+        assert_eq!(EventPriority::classify(&envelope), EventPriority::Telemetry);
+    }
+}
@@ -1,6 +1,6 @@
 // Path and File Name : /home/ransomeye/rebuild/ransomeye_linux_agent/agent/src/process.rs
 // Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
-// Details of functionality of this file: Process monitoring - exec, fork, mmap syscalls
+// Details of functionality of this file: Process monitoring - exec, fork, mmap syscalls, each linked into the per-boot process lineage chain
 
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
@@ -10,6 +10,7 @@ use std::time::{SystemTime, UNIX_EPOCH};
 use tracing::debug;
 
 use super::errors::AgentError;
+use super::lineage::{LineageChain, LineageInput, LineageLink};
 
 /// Process event types
 #[derive(Debug, Clone, PartialEq, serde::Serialize)]
@@ -33,14 +34,17 @@ pub struct ProcessEvent {
     pub timestamp: u64,
     pub mmap_address: Option<u64>,
     pub mmap_size: Option<u64>,
+    /// Link into the per-boot lineage chain (see lineage.rs)
+    pub lineage: LineageLink,
 }
 
 /// Process monitor
 /// 
 /// Tracks process events: exec, fork, mmap.
-/// Bounded memory for process tracking.
+/// Bounded memory for process tracking; an evicted process's next event links to the genesis.
 pub struct ProcessMonitor {
     processes: Arc<RwLock<HashMap<u32, ProcessInfo>>>,
+    lineage: LineageChain,
     max_processes: usize,
     events_processed: Arc<AtomicU64>,
 }
@@ -54,13 +58,21 @@ struct ProcessInfo {
     executable: Option<String>,
     first_seen: u64,
     last_seen: u64,
+    /// Hash of this process's latest event
+    lineage_hash: String,
 }
 
 impl ProcessMonitor {
     /// Create new process monitor
     pub fn new(max_processes: usize) -> Self {
+        Self::with_lineage(max_processes, LineageChain::for_this_boot())
+    }
+    
+    /// Create process monitor on an explicit lineage chain
+    pub fn with_lineage(max_processes: usize, lineage: LineageChain) -> Self {
         Self {
             processes: Arc::new(RwLock::new(HashMap::new())),
+            lineage,
             max_processes,
             events_processed: Arc::new(AtomicU64::new(0)),
         }
@@ -74,8 +86,8 @@ impl ProcessMonitor {
             .map_err(|e| AgentError::ProcessMonitoringFailed(format!("Time error: {}", e)))?
             .as_secs();
         
-        // Update process info
-        {
+        // Update process info; exec continues the pid's own lineage (fork then exec), else its parent's
+        let lineage = {
             let mut processes = self.processes.write();
             
             // Check memory bound
@@ -83,6 +95,21 @@ impl ProcessMonitor {
                 self.evict_oldest(&mut processes);
             }
             
+            let parent_hash = processes.get(&pid)
+                .or_else(|| ppid.and_then(|p| processes.get(&p)))
+                .map(|p| p.lineage_hash.clone());
+            let lineage = self.lineage.link(&LineageInput {
+                event_type: "Exec",
+                pid,
+                ppid,
+                uid,
+                gid,
+                executable: Some(&executable),
+                command_line: command_line.as_deref(),
+                mmap_address: None,
+                mmap_size: None,
+            }, parent_hash.as_deref());
+            
             processes.insert(pid, ProcessInfo {
                 pid,
                 ppid,
@@ -91,8 +118,10 @@ impl ProcessMonitor {
                 executable: Some(executable.clone()),
                 first_seen: timestamp,
                 last_seen: timestamp,
+                lineage_hash: lineage.hash.clone(),
             });
-        }
+            lineage
+        };
         
         self.events_processed.fetch_add(1, Ordering::Relaxed);
         
@@ -109,6 +138,7 @@ impl ProcessMonitor {
             timestamp,
             mmap_address: None,
             mmap_size: None,
+            lineage,
         })
     }
     
@@ -119,8 +149,8 @@ impl ProcessMonitor {
             .map_err(|e| AgentError::ProcessMonitoringFailed(format!("Time error: {}", e)))?
             .as_secs();
         
-        // Update process info for child
-        {
+        // Update process info for child; the fork links to the parent's latest event
+        let lineage = {
             let mut processes = self.processes.write();
             
             if processes.len() >= self.max_processes {
@@ -130,6 +160,18 @@ impl ProcessMonitor {
             // Get parent info if available
             let parent_info = processes.get(&parent_pid).cloned();
             
+            let lineage = self.lineage.link(&LineageInput {
+                event_type: "Fork",
+                pid: child_pid,
+                ppid: Some(parent_pid),
+                uid,
+                gid,
+                executable: None,
+                command_line: None,
+                mmap_address: None,
+                mmap_size: None,
+            }, parent_info.as_ref().map(|p| p.lineage_hash.as_str()));
+            
             processes.insert(child_pid, ProcessInfo {
                 pid: child_pid,
                 ppid: Some(parent_pid),
@@ -138,8 +180,10 @@ impl ProcessMonitor {
                 executable: parent_info.and_then(|p| p.executable),
                 first_seen: timestamp,
                 last_seen: timestamp,
+                lineage_hash: lineage.hash.clone(),
             });
-        }
+            lineage
+        };
         
         self.events_processed.fetch_add(1, Ordering::Relaxed);
         
@@ -156,6 +200,7 @@ impl ProcessMonitor {
             timestamp,
             mmap_address: None,
             mmap_size: None,
+            lineage,
         })
     }
     
@@ -166,13 +211,27 @@ impl ProcessMonitor {
             .map_err(|e| AgentError::ProcessMonitoringFailed(format!("Time error: {}", e)))?
             .as_secs();
         
-        // Update process info
-        {
+        // Update process info; mmap continues the pid's own lineage
+        let lineage = {
             let mut processes = self.processes.write();
-            if let Some(process) = processes.get_mut(&pid) {
+            let process = processes.get_mut(&pid);
+            let lineage = self.lineage.link(&LineageInput {
+                event_type: "Mmap",
+                pid,
+                ppid: None,
+                uid: 0,
+                gid: 0,
+                executable: None,
+                command_line: None,
+                mmap_address: Some(address),
+                mmap_size: Some(size),
+            }, process.as_ref().map(|p| p.lineage_hash.as_str()));
+            if let Some(process) = process {
                 process.last_seen = timestamp;
+                process.lineage_hash = lineage.hash.clone();
             }
-        }
+            lineage
+        };
         
         self.events_processed.fetch_add(1, Ordering::Relaxed);
         
@@ -189,6 +248,7 @@ impl ProcessMonitor {
             timestamp,
             mmap_address: Some(address),
             mmap_size: Some(size),
+            lineage,
         })
     }
     
@@ -0,0 +1,156 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_linux_agent/agent/src/signing_pool.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Worker thread pool for per-event hashing and Ed25519 signing, with results handed back in submission order
+
+/*
+ * Signing Pool
+ *
+ * Canonical serialization, SHA-256 and Ed25519 are the CPU-heavy part of every event. They run
+ * on a few dedicated threads instead of the async runtime that also drives the monitors. Jobs
+ * are numbered when submitted; workers finish them in any order and the results are released
+ * strictly by that number, so whatever reads them (admission to the delivery queue) sees events
+ * in envelope sequence order. Jobs in flight, from submission until the result is released,
+ * are bounded: a full pool makes `submit` wait, which backs up the channels in front of it.
+ */
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, OwnedSemaphorePermit, Semaphore};
+use tracing::{debug, warn};
+
+/// Submission side of the pool; dropping it lets the workers finish and exit
+pub struct PoolSubmitter<J> {
+    jobs: mpsc::Sender<(u64, J, OwnedSemaphorePermit)>,
+    in_flight: Arc<Semaphore>,
+    next_ticket: u64,
+}
+
+impl<J> PoolSubmitter<J> {
+    /// Queue a job, waiting while the pool is full. False once the pool has shut down.
+    pub async fn submit(&mut self, job: J) -> bool {
+        let Ok(permit) = self.in_flight.clone().acquire_owned().await else {
+            return false;
+        };
+        let ticket = self.next_ticket;
+        self.next_ticket += 1;
+        self.jobs.send((ticket, job, permit)).await.is_ok()
+    }
+}
+
+/// Results in submission order
+pub struct OrderedResults<R> {
+    results: mpsc::UnboundedReceiver<(u64, R, OwnedSemaphorePermit)>,
+    /// Finished out of order, waiting for an earlier ticket
+    pending: BTreeMap<u64, (R, OwnedSemaphorePermit)>,
+    next_ticket: u64,
+}
+
+impl<R> OrderedResults<R> {
+    /// Result of the next job in submission order; None once the submitter is dropped and
+    /// every job has been handed back
+    pub async fn next(&mut self) -> Option<R> {
+        loop {
+            if let Some((result, _permit)) = self.pending.remove(&self.next_ticket) {
+                self.next_ticket += 1;
+                return Some(result);
+            }
+            match self.results.recv().await {
+                Some((ticket, result, permit)) => {
+                    self.pending.insert(ticket, (result, permit));
+                }
+                None => {
+                    // Only a worker that died with its job leaves a gap; skip it rather than
+                    // hold back everything behind it
+                    let (ticket, (result, _permit)) = self.pending.pop_first()?;
+                    warn!("Signing pool: jobs {}..{} lost, continuing at {}", self.next_ticket, ticket, ticket);
+                    self.next_ticket = ticket + 1;
+                    return Some(result);
+                }
+            }
+        }
+    }
+}
+
+/// Start `workers` threads running `work`, with at most `max_in_flight` jobs between submission
+/// and the release of their result
+pub fn start<J, R, F>(workers: usize, max_in_flight: usize, work: F) -> (PoolSubmitter<J>, OrderedResults<R>)
+where
+    J: Send + 'static,
+    R: Send + 'static,
+    F: Fn(J) -> R + Send + Sync + 'static,
+{
+    let workers = workers.max(1);
+    let max_in_flight = max_in_flight.max(workers);
+    let (job_tx, job_rx) = mpsc::channel::<(u64, J, OwnedSemaphorePermit)>(max_in_flight);
+    let (result_tx, result_rx) = mpsc::unbounded_channel();
+    let job_rx = Arc::new(Mutex::new(job_rx));
+    let work = Arc::new(work);
+    for index in 0..workers {
+        let job_rx = job_rx.clone();
+        let result_tx = result_tx.clone();
+        let work = work.clone();
+        std::thread::Builder::new()
+            .name(format!("signing-{}", index))
+            .spawn(move || {
+                loop {
+                    // One idle worker waits on the channel at a time; the lock is released as
+                    // soon as it has a job
+                    let next = match job_rx.lock() {
+                        Ok(mut rx) => rx.blocking_recv(),
+                        Err(_) => None,
+                    };
+                    let Some((ticket, job, permit)) = next else { break };
+                    if result_tx.send((ticket, work(job), permit)).is_err() {
+                        break;
+                    }
+                }
+                debug!("Signing worker {} stopped", index);
+            })
+            .expect("failed to spawn signing worker thread");
+    }
+    (
+        PoolSubmitter { jobs: job_tx, in_flight: Arc::new(Semaphore::new(max_in_flight)), next_ticket: 0 },
+        OrderedResults { results: result_rx, pending: BTreeMap::new(), next_ticket: 0 },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_results_released_in_submission_order() {
+        // Earlier jobs take longer, so workers finish them last
+        let (mut submitter, mut results) = start(4, 16, |n: u64| {
+            std::thread::sleep(Duration::from_millis((20 - n) * 2));
+            n
+        });
+        let producer = tokio::spawn(async move {
+            for n in 0..20 {
+                assert!(submitter.submit(n).await);
+            }
+        });
+        let mut seen = Vec::new();
+        while let Some(n) = results.next().await {
+            seen.push(n);
+        }
+        producer.await.unwrap();
+        assert_eq!(seen, (0..20).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_in_flight_bound_holds_submitter() {
+        let (mut submitter, mut results) = start(1, 2, |n: u32| n);
+        assert!(submitter.submit(1).await);
+        assert!(submitter.submit(2).await);
+        // Both permits are held until their results are read
+        assert!(tokio::time::timeout(Duration::from_millis(100), submitter.submit(3)).await.is_err());
+        assert_eq!(results.next().await, Some(1));
+        assert!(tokio::time::timeout(Duration::from_secs(5), submitter.submit(4)).await.unwrap());
+        drop(submitter);
+        assert_eq!(results.next().await, Some(2));
+        assert_eq!(results.next().await, Some(4));
+        assert_eq!(results.next().await, None);
+    }
+}
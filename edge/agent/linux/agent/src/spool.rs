@@ -0,0 +1,662 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_linux_agent/agent/src/spool.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Bounded on-disk spool for signed events that could not be delivered to Core - encrypted size-rotated segments, zstd-compressed once sealed
+
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use parking_lot::Mutex;
+use tracing::{info, warn};
+
+use super::errors::AgentError;
+use super::spool_crypto::{SpoolCipher, RECORD_OVERHEAD};
+
+const ACTIVE_EXT: &str = "eseg";
+const SEALED_EXT: &str = "ezst";
+const TMP_EXT: &str = "tmp";
+/// One-file-per-event layout of earlier agent versions; imported on open
+const LEGACY_EXT: &str = "event";
+/// Unencrypted segment layout of earlier agent versions; imported on open
+const PLAIN_ACTIVE_EXT: &str = "seg";
+const PLAIN_SEALED_EXT: &str = "zst";
+const ACK_FILE: &str = "ack";
+const ZSTD_LEVEL: i32 = 3;
+/// Default uncompressed size at which the active segment is sealed
+pub const DEFAULT_SEGMENT_KB: u64 = 1024;
+
+/// Event spool
+///
+/// Events are appended to an active segment (`<first_seq>.eseg`, length-prefixed records, each
+/// encrypted on its own) and delivered oldest-first. At the segment size the active segment is
+/// rotated: sealed into a zstd-compressed, encrypted `<first_seq>-<count>.ezst` and a new segment
+/// starts with the next event. Encryption (see `spool_crypto`) binds every record and sealed
+/// segment to its sequence numbers; events are only decrypted when they are replayed.
+/// Delivery progress within the oldest segment is kept in `ack`; a crash can re-deliver a few
+/// events (ingest dedupes) but never loses acknowledged ones.
+///
+/// Bounded by on-disk size: when full, the OLDEST segment is dropped (its events counted),
+/// never the newest. The bound can be tightened at runtime by the agent's disk budget.
+/// Survives restarts - existing segments are re-indexed on open.
+pub struct EventSpool {
+    dir: PathBuf,
+    max_bytes: u64,
+    segment_bytes: u64,
+    cipher: SpoolCipher,
+    index: Mutex<SpoolIndex>,
+}
+
+struct Segment {
+    first_seq: u64,
+    count: u64,
+    disk_bytes: u64,
+    path: PathBuf,
+    compressed: bool,
+}
+
+struct SpoolIndex {
+    segments: VecDeque<Segment>,
+    /// Append handle of the last segment while it is still active
+    active: Option<File>,
+    /// Events of the oldest segment already delivered
+    acked: u64,
+    /// Decrypted records of the oldest segment (first_seq, records)
+    head: Option<(u64, Vec<Vec<u8>>)>,
+    total_bytes: u64,
+    limit_bytes: u64,
+    next_seq: u64,
+    dropped: u64,
+}
+
+impl EventSpool {
+    /// Open (or create) the spool directory and index existing segments. `cipher` must be keyed
+    /// the same way across restarts; segments it cannot open are dropped when reached.
+    pub fn open(dir: &Path, max_size_mb: u64, cipher: SpoolCipher) -> Result<Self, AgentError> {
+        fs::create_dir_all(dir)
+            .map_err(|e| AgentError::SpoolError(format!("Failed to create spool dir {}: {}", dir.display(), e)))?;
+
+        let mut segments = Vec::new();
+        let mut plain_segments = Vec::new();
+        let mut legacy = Vec::new();
+        let read_dir = fs::read_dir(dir)
+            .map_err(|e| AgentError::SpoolError(format!("Failed to read spool dir {}: {}", dir.display(), e)))?;
+        for entry in read_dir.flatten() {
+            let path = entry.path();
+            let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default().to_string();
+            let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            match path.extension().and_then(|e| e.to_str()) {
+                // Partial write or seal from a crash - the source data is still on disk
+                Some(TMP_EXT) => {
+                    let _ = fs::remove_file(&path);
+                }
+                Some(ext @ (SEALED_EXT | PLAIN_SEALED_EXT)) => {
+                    let parsed = stem.split_once('-')
+                        .and_then(|(first, count)| Some((first.parse::<u64>().ok()?, count.parse::<u64>().ok()?)));
+                    if let Some((first_seq, count)) = parsed {
+                        let encrypted = ext == SEALED_EXT;
+                        let segment = Segment { first_seq, count, disk_bytes: size, path, compressed: true };
+                        if encrypted { segments.push(segment) } else { plain_segments.push(segment) }
+                    }
+                }
+                Some(ACTIVE_EXT) => {
+                    if let Ok(first_seq) = stem.parse::<u64>() {
+                        let (count, valid_len) = scan_records(&path)?;
+                        if valid_len < size {
+                            // Torn append from a crash: keep the complete records only
+                            warn!("Spool segment {} has a partial record, truncating", path.display());
+                            truncate(&path, valid_len)?;
+                        }
+                        segments.push(Segment { first_seq, count, disk_bytes: valid_len, path, compressed: false });
+                    }
+                }
+                Some(PLAIN_ACTIVE_EXT) => {
+                    if let Ok(first_seq) = stem.parse::<u64>() {
+                        let (count, _) = scan_records(&path)?;
+                        plain_segments.push(Segment { first_seq, count, disk_bytes: size, path, compressed: false });
+                    }
+                }
+                Some(LEGACY_EXT) => {
+                    if let Ok(seq) = stem.parse::<u64>() {
+                        legacy.push((seq, path));
+                    }
+                }
+                _ => {}
+            }
+        }
+        legacy.sort_unstable();
+        let segments: VecDeque<Segment> = dedupe_segments(segments).into();
+        let plain_segments = dedupe_segments(plain_segments);
+
+        let acked = read_ack(dir, segments.front());
+        // The cursor of an unencrypted spool refers to its oldest segment; it is consumed by the import
+        let plain_acked = read_ack(dir, plain_segments.first());
+        if !plain_segments.is_empty() {
+            let _ = fs::remove_file(dir.join(ACK_FILE));
+        }
+        let total_bytes = segments.iter().map(|s| s.disk_bytes).sum();
+        let next_seq = segments.back().map(|s| s.first_seq + s.count).unwrap_or(0);
+        let pending: u64 = segments.iter().map(|s| s.count).sum::<u64>() - acked;
+        if pending > 0 {
+            info!("Spool recovered {} events ({} bytes in {} segments) from {}", pending, total_bytes, segments.len(), dir.display());
+        }
+
+        let max_bytes = max_size_mb * 1024 * 1024;
+        let spool = Self {
+            dir: dir.to_path_buf(),
+            max_bytes,
+            segment_bytes: DEFAULT_SEGMENT_KB * 1024,
+            cipher,
+            index: Mutex::new(SpoolIndex {
+                segments,
+                active: None,
+                acked,
+                head: None,
+                total_bytes,
+                limit_bytes: max_bytes,
+                next_seq,
+                dropped: 0,
+            }),
+        };
+
+        // Reopen the last uncompressed segment for appending
+        {
+            let mut index = spool.index.lock();
+            if let Some(last) = index.segments.back().filter(|s| !s.compressed) {
+                let file = OpenOptions::new().append(true).open(&last.path)
+                    .map_err(|e| AgentError::SpoolError(format!("Failed to reopen spool segment {}: {}", last.path.display(), e)))?;
+                index.active = Some(file);
+            }
+        }
+
+        if !legacy.is_empty() {
+            info!("Importing {} events from legacy spool layout", legacy.len());
+            for (_, path) in legacy {
+                if let Ok(body) = fs::read(&path) {
+                    spool.push(&body)?;
+                }
+                let _ = fs::remove_file(&path);
+            }
+        }
+        if !plain_segments.is_empty() {
+            info!("Encrypting {} unencrypted spool segments", plain_segments.len());
+            for (i, segment) in plain_segments.iter().enumerate() {
+                match read_plain_segment(segment) {
+                    Ok(records) => {
+                        let skip = if i == 0 { plain_acked as usize } else { 0 };
+                        for body in records.iter().skip(skip) {
+                            spool.push(body)?;
+                        }
+                    }
+                    Err(e) => warn!("Dropping unreadable spool segment {}: {}", segment.path.display(), e),
+                }
+                let _ = fs::remove_file(&segment.path);
+            }
+        }
+        Ok(spool)
+    }
+
+    /// Rotate the active segment at `kb` KiB of uncompressed events (default 1024)
+    pub fn with_segment_kb(mut self, kb: u64) -> Self {
+        self.segment_bytes = kb.max(1) * 1024;
+        self
+    }
+
+    /// Spool an event, dropping the oldest segments if the spool is full
+    pub fn push(&self, body: &[u8]) -> Result<(), AgentError> {
+        let size = (4 + RECORD_OVERHEAD + body.len()) as u64;
+        let mut index = self.index.lock();
+
+        if size > index.limit_bytes {
+            index.dropped += 1;
+            return Err(AgentError::SpoolError(format!("Event of {} bytes exceeds spool capacity {}", size, index.limit_bytes)));
+        }
+
+        let limit = index.limit_bytes;
+        self.trim_to(&mut index, limit - size);
+
+        if index.active.is_none() {
+            let first_seq = index.next_seq;
+            let path = self.dir.join(format!("{:020}.{}", first_seq, ACTIVE_EXT));
+            let file = OpenOptions::new().create(true).append(true).open(&path)
+                .map_err(|e| AgentError::SpoolError(format!("Failed to create spool segment {}: {}", path.display(), e)))?;
+            index.segments.push_back(Segment { first_seq, count: 0, disk_bytes: 0, path, compressed: false });
+            index.active = Some(file);
+        }
+
+        let seq = index.next_seq;
+        let sealed = self.cipher.seal_record(seq, body)?;
+        let mut record = Vec::with_capacity(size as usize);
+        record.extend_from_slice(&(sealed.len() as u32).to_le_bytes());
+        record.extend_from_slice(&sealed);
+        let file = index.active.as_mut().expect("active segment opened above");
+        file.write_all(&record)
+            .and_then(|_| file.sync_data())
+            .map_err(|e| AgentError::SpoolError(format!("Failed to spool event {}: {}", seq, e)))?;
+
+        index.next_seq += 1;
+        index.total_bytes += size;
+        let segment = index.segments.back_mut().expect("active segment present");
+        segment.count += 1;
+        segment.disk_bytes += size;
+        // A tight limit shrinks segments too, so trimming never has to drop the whole spool at once
+        let rotate_at = self.segment_bytes.min(index.limit_bytes / 4);
+        let segment = index.segments.back().expect("active segment present");
+        if segment.disk_bytes >= rotate_at {
+            self.seal_active(&mut index);
+        }
+        Ok(())
+    }
+
+    /// Oldest spooled event (not removed until `remove` acknowledges delivery)
+    pub fn peek_oldest(&self) -> Result<Option<(u64, Vec<u8>)>, AgentError> {
+        let mut index = self.index.lock();
+        loop {
+            let Some(front) = index.segments.front() else { return Ok(None) };
+            let (first_seq, count) = (front.first_seq, front.count);
+            if index.acked >= count {
+                self.pop_front(&mut index);
+                continue;
+            }
+
+            // Cached records of the active segment go stale as events are appended
+            let cached = matches!(&index.head, Some((seq, records)) if *seq == first_seq && records.len() as u64 >= count);
+            if !cached {
+                match read_segment(front, &self.cipher) {
+                    Ok(records) => index.head = Some((first_seq, records)),
+                    Err(e) => {
+                        // Unreadable segment can never be delivered - drop it rather than wedge the spool
+                        warn!("Dropping unreadable spool segment {}: {}", first_seq, e);
+                        index.dropped += count - index.acked;
+                        self.pop_front(&mut index);
+                        continue;
+                    }
+                }
+            }
+
+            let acked = index.acked as usize;
+            let body = index.head.as_ref().and_then(|(_, records)| records.get(acked).cloned());
+            match body {
+                Some(body) => return Ok(Some((first_seq + acked as u64, body))),
+                None => {
+                    warn!("Spool segment {} holds fewer records than indexed, dropping", first_seq);
+                    index.dropped += count - index.acked;
+                    self.pop_front(&mut index);
+                }
+            }
+        }
+    }
+
+    /// Remove a delivered (or permanently rejected) event. Only the oldest event can be removed;
+    /// anything else was already dropped.
+    pub fn remove(&self, seq: u64) -> Result<(), AgentError> {
+        let mut index = self.index.lock();
+        let Some(front) = index.segments.front() else { return Ok(()) };
+        if seq != front.first_seq + index.acked {
+            return Ok(());
+        }
+        let (first_seq, count) = (front.first_seq, front.count);
+        index.acked += 1;
+        // Fully delivered segment (even the active one) is deleted; the next push starts a new one
+        if index.acked >= count {
+            self.pop_front(&mut index);
+        } else {
+            write_ack(&self.dir, first_seq, index.acked)?;
+        }
+        Ok(())
+    }
+
+    /// Tighten (or relax, up to the configured maximum) the on-disk bound; drops oldest segments to fit
+    pub fn set_limit_bytes(&self, limit: u64) {
+        let mut index = self.index.lock();
+        index.limit_bytes = limit.min(self.max_bytes);
+        let limit = index.limit_bytes;
+        self.trim_to(&mut index, limit);
+    }
+
+    /// Number of spooled events
+    pub fn len(&self) -> usize {
+        let index = self.index.lock();
+        (index.segments.iter().map(|s| s.count).sum::<u64>() - index.acked) as usize
+    }
+
+    /// Check if spool is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Bytes currently spooled on disk (compressed size for sealed segments)
+    pub fn bytes(&self) -> u64 {
+        self.index.lock().total_bytes
+    }
+
+    /// Events dropped because the spool was full or unreadable
+    pub fn dropped(&self) -> u64 {
+        self.index.lock().dropped
+    }
+
+    /// Configured upper bound (AGENT_SPOOL_MAX_MB)
+    pub fn max_bytes(&self) -> u64 {
+        self.max_bytes
+    }
+
+    /// Drop oldest segments until `total_bytes <= target`
+    fn trim_to(&self, index: &mut SpoolIndex, target: u64) {
+        while index.total_bytes > target {
+            let Some(front) = index.segments.front() else { break };
+            let lost = front.count - index.acked;
+            warn!("Spool full ({} bytes max): dropped oldest segment {} ({} events)", index.limit_bytes, front.first_seq, lost);
+            index.dropped += lost;
+            self.pop_front(index);
+        }
+    }
+
+    /// Delete the oldest segment and reset the delivery cursor
+    fn pop_front(&self, index: &mut SpoolIndex) {
+        let Some(segment) = index.segments.pop_front() else { return };
+        let _ = fs::remove_file(&segment.path);
+        index.total_bytes -= segment.disk_bytes;
+        index.acked = 0;
+        index.head = None;
+        if index.segments.is_empty() {
+            index.active = None;
+        }
+        let _ = fs::remove_file(self.dir.join(ACK_FILE));
+    }
+
+    /// Rotate: compress and re-encrypt the active segment as one unit and stop appending to it.
+    /// On failure the segment stays on disk as it is and is still delivered.
+    fn seal_active(&self, index: &mut SpoolIndex) {
+        index.active = None;
+        let Some(segment) = index.segments.back_mut() else { return };
+        let sealed_path = self.dir.join(format!("{:020}-{}.{}", segment.first_seq, segment.count, SEALED_EXT));
+        let tmp_path = sealed_path.with_extension(TMP_EXT);
+        let seal = || -> Result<u64, AgentError> {
+            let records = read_segment(segment, &self.cipher)?;
+            let compressed = zstd::encode_all(encode_records(&records).as_slice(), ZSTD_LEVEL)
+                .map_err(|e| AgentError::SpoolError(format!("compression failed: {}", e)))?;
+            let sealed = self.cipher.seal_segment(segment.first_seq, segment.count, &compressed)?;
+            File::create(&tmp_path)
+                .and_then(|mut file| {
+                    file.write_all(&sealed)?;
+                    file.sync_all()
+                })
+                .and_then(|_| fs::rename(&tmp_path, &sealed_path))
+                .map_err(|e| AgentError::SpoolError(format!("write failed: {}", e)))?;
+            Ok(sealed.len() as u64)
+        };
+        match seal() {
+            Ok(compressed_bytes) => {
+                let _ = fs::remove_file(&segment.path);
+                index.total_bytes = index.total_bytes - segment.disk_bytes + compressed_bytes;
+                segment.disk_bytes = compressed_bytes;
+                segment.path = sealed_path;
+                segment.compressed = true;
+            }
+            Err(e) => {
+                let _ = fs::remove_file(&tmp_path);
+                warn!("Failed to seal spool segment {} (kept as is): {}", segment.first_seq, e);
+            }
+        }
+    }
+}
+
+/// Count complete records in an uncompressed segment; returns (records, valid byte length)
+fn scan_records(path: &Path) -> Result<(u64, u64), AgentError> {
+    let raw = fs::read(path)
+        .map_err(|e| AgentError::SpoolError(format!("Failed to read spool segment {}: {}", path.display(), e)))?;
+    let (records, valid_len) = decode_records(&raw);
+    Ok((records.len() as u64, valid_len as u64))
+}
+
+fn decode_records(raw: &[u8]) -> (Vec<Vec<u8>>, usize) {
+    let mut records = Vec::new();
+    let mut offset = 0;
+    while offset + 4 <= raw.len() {
+        let len = u32::from_le_bytes([raw[offset], raw[offset + 1], raw[offset + 2], raw[offset + 3]]) as usize;
+        if offset + 4 + len > raw.len() {
+            break;
+        }
+        records.push(raw[offset + 4..offset + 4 + len].to_vec());
+        offset += 4 + len;
+    }
+    (records, offset)
+}
+
+fn encode_records(records: &[Vec<u8>]) -> Vec<u8> {
+    let mut raw = Vec::with_capacity(records.iter().map(|r| 4 + r.len()).sum());
+    for record in records {
+        raw.extend_from_slice(&(record.len() as u32).to_le_bytes());
+        raw.extend_from_slice(record);
+    }
+    raw
+}
+
+/// Decrypted records of a segment, in sequence order
+fn read_segment(segment: &Segment, cipher: &SpoolCipher) -> Result<Vec<Vec<u8>>, AgentError> {
+    let raw = fs::read(&segment.path)
+        .map_err(|e| AgentError::SpoolError(format!("Failed to read spool segment {}: {}", segment.path.display(), e)))?;
+    if segment.compressed {
+        let compressed = cipher.open_segment(segment.first_seq, segment.count, &raw)?;
+        let raw = zstd::decode_all(compressed.as_slice())
+            .map_err(|e| AgentError::SpoolError(format!("spool segment {}: decompression failed: {}", segment.first_seq, e)))?;
+        return Ok(decode_records(&raw).0);
+    }
+    decode_records(&raw).0
+        .iter()
+        .enumerate()
+        .map(|(i, sealed)| cipher.open_record(segment.first_seq + i as u64, sealed))
+        .collect()
+}
+
+/// Records of a segment written by an agent without spool encryption
+fn read_plain_segment(segment: &Segment) -> std::io::Result<Vec<Vec<u8>>> {
+    let raw = fs::read(&segment.path)?;
+    let raw = if segment.compressed { zstd::decode_all(raw.as_slice())? } else { raw };
+    Ok(decode_records(&raw).0)
+}
+
+/// A seal interrupted between rename and delete leaves both files; the sealed copy wins
+fn dedupe_segments(mut segments: Vec<Segment>) -> Vec<Segment> {
+    segments.sort_unstable_by_key(|s| (s.first_seq, !s.compressed));
+    let mut deduped: Vec<Segment> = Vec::with_capacity(segments.len());
+    for segment in segments {
+        if deduped.last().is_some_and(|prev| prev.first_seq == segment.first_seq) {
+            let _ = fs::remove_file(&segment.path);
+            continue;
+        }
+        deduped.push(segment);
+    }
+    deduped
+}
+
+fn truncate(path: &Path, len: u64) -> Result<(), AgentError> {
+    OpenOptions::new().write(true).open(path)
+        .and_then(|f| f.set_len(len))
+        .map_err(|e| AgentError::SpoolError(format!("Failed to truncate spool segment {}: {}", path.display(), e)))
+}
+
+/// Delivery cursor for the oldest segment (`<first_seq> <acked>`); ignored if it names another segment
+fn read_ack(dir: &Path, front: Option<&Segment>) -> u64 {
+    let Some(front) = front else { return 0 };
+    let Ok(raw) = fs::read_to_string(dir.join(ACK_FILE)) else { return 0 };
+    let mut parts = raw.split_whitespace().map(|p| p.parse::<u64>().ok());
+    match (parts.next().flatten(), parts.next().flatten()) {
+        (Some(first_seq), Some(acked)) if first_seq == front.first_seq => acked.min(front.count),
+        _ => 0,
+    }
+}
+
+fn write_ack(dir: &Path, first_seq: u64, acked: u64) -> Result<(), AgentError> {
+    let tmp = dir.join(format!("{}.{}", ACK_FILE, TMP_EXT));
+    fs::write(&tmp, format!("{} {}", first_seq, acked))
+        .and_then(|_| fs::rename(&tmp, dir.join(ACK_FILE)))
+        .map_err(|e| AgentError::SpoolError(format!("Failed to persist spool cursor: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn cipher() -> SpoolCipher {
+        SpoolCipher::new(&[7u8; 32])
+    }
+
+    fn drain(spool: &EventSpool) -> Vec<Vec<u8>> {
+        let mut out = Vec::new();
+        while let Some((seq, body)) = spool.peek_oldest().unwrap() {
+            spool.remove(seq).unwrap();
+            out.push(body);
+        }
+        out
+    }
+
+    #[test]
+    fn test_spool_is_fifo_and_survives_reopen() {
+        let dir = TempDir::new().unwrap();
+        {
+            let spool = EventSpool::open(dir.path(), 1, cipher()).unwrap();
+            spool.push(b"first").unwrap();
+            spool.push(b"second").unwrap();
+            assert_eq!(spool.len(), 2);
+        }
+
+        let spool = EventSpool::open(dir.path(), 1, cipher()).unwrap();
+        assert_eq!(spool.len(), 2);
+        let (seq, body) = spool.peek_oldest().unwrap().unwrap();
+        assert_eq!(body, b"first");
+        spool.remove(seq).unwrap();
+        spool.push(b"third").unwrap();
+
+        let (_, body) = spool.peek_oldest().unwrap().unwrap();
+        assert_eq!(body, b"second");
+        assert_eq!(drain(&spool), vec![b"second".to_vec(), b"third".to_vec()]);
+        assert!(spool.is_empty());
+    }
+
+    #[test]
+    fn test_segments_rotate_compressed_and_cursor_survives_reopen() {
+        let dir = TempDir::new().unwrap();
+        let event = vec![b'a'; 600];
+        {
+            let spool = EventSpool::open(dir.path(), 1, cipher()).unwrap().with_segment_kb(1);
+            for _ in 0..10 {
+                spool.push(&event).unwrap();
+            }
+            // Highly compressible events: sealed segments take far less than the raw bytes
+            assert!(spool.bytes() < 10 * 604);
+            let (seq, _) = spool.peek_oldest().unwrap().unwrap();
+            spool.remove(seq).unwrap();
+        }
+
+        let sealed = fs::read_dir(dir.path()).unwrap().flatten()
+            .filter(|e| e.path().extension().and_then(|x| x.to_str()) == Some(SEALED_EXT))
+            .count();
+        assert_eq!(sealed, 5);
+
+        let spool = EventSpool::open(dir.path(), 1, cipher()).unwrap().with_segment_kb(1);
+        assert_eq!(spool.len(), 9);
+        assert_eq!(drain(&spool).len(), 9);
+        assert_eq!(spool.bytes(), 0);
+    }
+
+    #[test]
+    fn test_spool_drops_oldest_when_full() {
+        let dir = TempDir::new().unwrap();
+        let spool = EventSpool::open(dir.path(), 1, cipher()).unwrap().with_segment_kb(1);
+        // Incompressible events (xorshift) so compression does not hide the byte bound
+        let mut x = 0x9E37_79B9_7F4A_7C15u64;
+        let chunk: Vec<u8> = (0..400 * 1024)
+            .map(|_| {
+                x ^= x << 13;
+                x ^= x >> 7;
+                x ^= x << 17;
+                x as u8
+            })
+            .collect();
+
+        spool.push(&chunk).unwrap();
+        spool.push(&chunk).unwrap();
+        spool.push(b"newest").unwrap();
+        spool.push(&chunk).unwrap();
+
+        assert_eq!(spool.dropped(), 1);
+        assert_eq!(spool.len(), 3);
+        assert!(spool.bytes() <= 1024 * 1024);
+        assert!(spool.push(&vec![b'x'; 2 * 1024 * 1024]).is_err());
+    }
+
+    #[test]
+    fn test_disk_budget_limit_trims_oldest() {
+        let dir = TempDir::new().unwrap();
+        let spool = EventSpool::open(dir.path(), 1, cipher()).unwrap();
+        spool.push(&vec![b'x'; 1000]).unwrap();
+        spool.push(&vec![b'y'; 1000]).unwrap();
+
+        spool.set_limit_bytes(100);
+        assert_eq!(spool.len(), 0);
+        assert_eq!(spool.dropped(), 2);
+        assert!(spool.push(&vec![b'z'; 1000]).is_err());
+
+        spool.set_limit_bytes(u64::MAX);
+        spool.push(&vec![b'z'; 1000]).unwrap();
+        assert_eq!(spool.len(), 1);
+    }
+
+    #[test]
+    fn test_legacy_event_files_are_imported() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join(format!("{:020}.event", 0)), b"old-0").unwrap();
+        fs::write(dir.path().join(format!("{:020}.event", 1)), b"old-1").unwrap();
+
+        let spool = EventSpool::open(dir.path(), 1, cipher()).unwrap();
+        assert_eq!(drain(&spool), vec![b"old-0".to_vec(), b"old-1".to_vec()]);
+    }
+
+    #[test]
+    fn test_spool_is_encrypted_at_rest_and_bound_to_its_key() {
+        let dir = TempDir::new().unwrap();
+        {
+            let spool = EventSpool::open(dir.path(), 1, cipher()).unwrap().with_segment_kb(1);
+            for i in 0..5 {
+                spool.push(format!("exec /usr/bin/secret-tool #{} {}", i, "x".repeat(300)).as_bytes()).unwrap();
+            }
+        }
+        let files: Vec<PathBuf> = fs::read_dir(dir.path()).unwrap().flatten().map(|e| e.path()).collect();
+        assert!(files.iter().any(|p| p.extension().and_then(|x| x.to_str()) == Some(SEALED_EXT)));
+        assert!(files.iter().any(|p| p.extension().and_then(|x| x.to_str()) == Some(ACTIVE_EXT)));
+        for path in &files {
+            let raw = fs::read(path).unwrap();
+            assert!(!raw.windows(11).any(|w| w == b"secret-tool"), "plaintext in {}", path.display());
+        }
+
+        // Another identity key cannot read the spool: segments are dropped, not delivered
+        let spool = EventSpool::open(dir.path(), 1, SpoolCipher::new(&[8u8; 32])).unwrap();
+        assert_eq!(spool.len(), 5);
+        assert!(drain(&spool).is_empty());
+        assert_eq!(spool.dropped(), 5);
+    }
+
+    #[test]
+    fn test_unencrypted_segments_are_imported_past_the_cursor() {
+        let dir = TempDir::new().unwrap();
+        let plain = |events: &[&[u8]]| encode_records(&events.iter().map(|e| e.to_vec()).collect::<Vec<_>>());
+        let sealed = zstd::encode_all(plain(&[b"old-0", b"old-1"]).as_slice(), ZSTD_LEVEL).unwrap();
+        fs::write(dir.path().join(format!("{:020}-2.{}", 0, PLAIN_SEALED_EXT)), sealed).unwrap();
+        fs::write(dir.path().join(format!("{:020}.{}", 2, PLAIN_ACTIVE_EXT)), plain(&[b"old-2"])).unwrap();
+        // old-0 was delivered before the upgrade
+        fs::write(dir.path().join(ACK_FILE), "0 1").unwrap();
+
+        {
+            let spool = EventSpool::open(dir.path(), 1, cipher()).unwrap();
+            assert_eq!(spool.len(), 2);
+        }
+        let leftover = fs::read_dir(dir.path()).unwrap().flatten()
+            .filter(|e| matches!(e.path().extension().and_then(|x| x.to_str()), Some(PLAIN_SEALED_EXT | PLAIN_ACTIVE_EXT)))
+            .count();
+        assert_eq!(leftover, 0);
+
+        let spool = EventSpool::open(dir.path(), 1, cipher()).unwrap();
+        assert_eq!(drain(&spool), vec![b"old-1".to_vec(), b"old-2".to_vec()]);
+    }
+}
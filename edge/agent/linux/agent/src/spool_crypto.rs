@@ -0,0 +1,175 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_linux_agent/agent/src/spool_crypto.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: At-rest encryption of the event spool - XChaCha20-Poly1305 with headers binding each record and sealed segment to its sequence range
+
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use rand::{rngs::OsRng, RngCore};
+
+use super::errors::AgentError;
+
+/// HKDF info under which the spool key is derived from the agent's Ed25519 identity key
+pub const SPOOL_KEY_CONTEXT: &[u8] = b"ransomeye/linux-agent/spool/v1";
+
+const MAGIC: &[u8; 4] = b"RESP";
+const VERSION: u8 = 1;
+const KIND_RECORD: u8 = 1;
+const KIND_SEGMENT: u8 = 2;
+/// magic, version, kind, first_seq (LE), count (LE)
+const HEADER_LEN: usize = 4 + 1 + 1 + 8 + 8;
+const NONCE_LEN: usize = 24;
+const TAG_LEN: usize = 16;
+/// Bytes a sealed record adds to its plaintext (nonce + tag)
+pub const RECORD_OVERHEAD: usize = NONCE_LEN + TAG_LEN;
+
+/// Authenticated header: which sequence numbers the ciphertext holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SpoolHeader {
+    kind: u8,
+    first_seq: u64,
+    count: u64,
+}
+
+impl SpoolHeader {
+    fn record(seq: u64) -> Self {
+        Self { kind: KIND_RECORD, first_seq: seq, count: 1 }
+    }
+
+    fn segment(first_seq: u64, count: u64) -> Self {
+        Self { kind: KIND_SEGMENT, first_seq, count }
+    }
+
+    fn encode(&self) -> [u8; HEADER_LEN] {
+        let mut out = [0u8; HEADER_LEN];
+        out[..4].copy_from_slice(MAGIC);
+        out[4] = VERSION;
+        out[5] = self.kind;
+        out[6..14].copy_from_slice(&self.first_seq.to_le_bytes());
+        out[14..22].copy_from_slice(&self.count.to_le_bytes());
+        out
+    }
+
+    fn decode(raw: &[u8]) -> Option<Self> {
+        if raw.len() < HEADER_LEN || &raw[..4] != MAGIC || raw[4] != VERSION {
+            return None;
+        }
+        Some(Self {
+            kind: raw[5],
+            first_seq: u64::from_le_bytes(raw[6..14].try_into().ok()?),
+            count: u64::from_le_bytes(raw[14..22].try_into().ok()?),
+        })
+    }
+}
+
+/// Spool cipher
+///
+/// Active segments hold one ciphertext per record (`nonce || ciphertext`); the record's sequence
+/// number is authenticated but not stored, so a record moved or replayed into another position
+/// fails to open. Sealed segments are compressed first and encrypted whole behind a stored
+/// header carrying the segment's sequence range, checked against the segment's file name.
+/// Plaintext only exists in memory, when a segment is sealed and when events are replayed.
+pub struct SpoolCipher {
+    cipher: XChaCha20Poly1305,
+}
+
+impl SpoolCipher {
+    pub fn new(key: &[u8; 32]) -> Self {
+        Self { cipher: XChaCha20Poly1305::new(Key::from_slice(key)) }
+    }
+
+    /// Encrypt the record with sequence number `seq`
+    pub fn seal_record(&self, seq: u64, plaintext: &[u8]) -> Result<Vec<u8>, AgentError> {
+        self.seal(&SpoolHeader::record(seq).encode(), plaintext)
+    }
+
+    pub fn open_record(&self, seq: u64, sealed: &[u8]) -> Result<Vec<u8>, AgentError> {
+        self.open(&SpoolHeader::record(seq).encode(), sealed)
+            .map_err(|e| AgentError::SpoolError(format!("spool record {}: {}", seq, e)))
+    }
+
+    /// Encrypt a sealed segment's (compressed) contents; the header is stored in front
+    pub fn seal_segment(&self, first_seq: u64, count: u64, plaintext: &[u8]) -> Result<Vec<u8>, AgentError> {
+        let header = SpoolHeader::segment(first_seq, count).encode();
+        let sealed = self.seal(&header, plaintext)?;
+        let mut out = Vec::with_capacity(HEADER_LEN + sealed.len());
+        out.extend_from_slice(&header);
+        out.extend_from_slice(&sealed);
+        Ok(out)
+    }
+
+    /// Decrypt a sealed segment, which must hold exactly `first_seq..first_seq + count`
+    pub fn open_segment(&self, first_seq: u64, count: u64, raw: &[u8]) -> Result<Vec<u8>, AgentError> {
+        let expected = SpoolHeader::segment(first_seq, count);
+        match SpoolHeader::decode(raw) {
+            Some(header) if header == expected => {}
+            Some(header) => {
+                return Err(AgentError::SpoolError(format!(
+                    "spool segment {} header covers {}+{} (kind {}), expected {}+{}",
+                    first_seq, header.first_seq, header.count, header.kind, first_seq, count
+                )))
+            }
+            None => return Err(AgentError::SpoolError(format!("spool segment {} has no valid header", first_seq))),
+        }
+        self.open(&raw[..HEADER_LEN], &raw[HEADER_LEN..])
+            .map_err(|e| AgentError::SpoolError(format!("spool segment {}: {}", first_seq, e)))
+    }
+
+    fn seal(&self, aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, AgentError> {
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+        let ciphertext = self.cipher
+            .encrypt(XNonce::from_slice(&nonce), Payload { msg: plaintext, aad })
+            .map_err(|_| AgentError::SpoolError("spool encryption failed".to_string()))?;
+        let mut out = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+        out.extend_from_slice(&nonce);
+        out.extend_from_slice(&ciphertext);
+        Ok(out)
+    }
+
+    fn open(&self, aad: &[u8], sealed: &[u8]) -> Result<Vec<u8>, &'static str> {
+        if sealed.len() < NONCE_LEN + TAG_LEN {
+            return Err("ciphertext truncated");
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        self.cipher
+            .decrypt(XNonce::from_slice(nonce), Payload { msg: ciphertext, aad })
+            .map_err(|_| "authentication failed (wrong key or tampered data)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records_are_bound_to_their_sequence() {
+        let cipher = SpoolCipher::new(&[7u8; 32]);
+        let sealed = cipher.seal_record(42, b"process exec /usr/bin/curl").unwrap();
+        assert_eq!(sealed.len(), b"process exec /usr/bin/curl".len() + RECORD_OVERHEAD);
+        assert!(!sealed.windows(4).any(|w| w == b"curl"));
+
+        assert_eq!(cipher.open_record(42, &sealed).unwrap(), b"process exec /usr/bin/curl");
+        assert!(cipher.open_record(43, &sealed).is_err());
+        assert!(SpoolCipher::new(&[8u8; 32]).open_record(42, &sealed).is_err());
+
+        let mut tampered = sealed.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert!(cipher.open_record(42, &tampered).is_err());
+    }
+
+    #[test]
+    fn test_segment_header_must_match_sequence_range() {
+        let cipher = SpoolCipher::new(&[7u8; 32]);
+        let sealed = cipher.seal_segment(100, 5, b"compressed records").unwrap();
+        assert_eq!(cipher.open_segment(100, 5, &sealed).unwrap(), b"compressed records");
+        assert!(cipher.open_segment(100, 4, &sealed).is_err());
+        assert!(cipher.open_segment(105, 5, &sealed).is_err());
+
+        // Rewriting the stored header to claim another range breaks authentication
+        let mut forged = sealed.clone();
+        forged[14..22].copy_from_slice(&4u64.to_le_bytes());
+        assert!(cipher.open_segment(100, 4, &forged).is_err());
+        assert!(cipher.open_segment(100, 5, &sealed[..HEADER_LEN + 10]).is_err());
+    }
+}
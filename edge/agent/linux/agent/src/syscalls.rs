@@ -1,76 +1,127 @@
 // Path and File Name : /home/ransomeye/rebuild/ransomeye_linux_agent/agent/src/syscalls.rs
 // Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
-// Details of functionality of this file: Syscall monitoring abstraction - eBPF/auditd
+// Details of functionality of this file: Syscall monitoring abstraction - eBPF (CO-RE) per hook with auditd fallback per hook
 
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
+use parking_lot::RwLock;
 use tracing::{warn, info};
 
 use super::errors::AgentError;
+use super::kernel_caps::{self, HookSource, KernelCapabilities, KernelProber};
 
 /// Syscall monitor
 /// 
 /// Abstracts eBPF and auditd for syscall monitoring.
-/// eBPF preferred, auditd fallback.
+/// eBPF preferred per hook, auditd fallback for the hooks eBPF cannot serve on this kernel.
 pub struct SyscallMonitor {
     ebpf_enabled: Arc<AtomicBool>,
     auditd_enabled: Arc<AtomicBool>,
     monitoring: Arc<AtomicBool>,
+    prober: KernelProber,
+    capabilities: RwLock<Option<KernelCapabilities>>,
 }
 
 impl SyscallMonitor {
     /// Create new syscall monitor
     pub fn new() -> Self {
+        Self::with_prober(KernelProber::new())
+    }
+    
+    /// Create syscall monitor probing kernel interfaces through `prober`
+    pub fn with_prober(prober: KernelProber) -> Self {
         Self {
             ebpf_enabled: Arc::new(AtomicBool::new(false)),
             auditd_enabled: Arc::new(AtomicBool::new(false)),
             monitoring: Arc::new(AtomicBool::new(false)),
+            prober,
+            capabilities: RwLock::new(None),
         }
     }
     
+    /// Re-probe the kernel for the currently enabled sources
+    fn reprobe(&self) -> KernelCapabilities {
+        let caps = self.prober.probe(self.is_ebpf_enabled(), self.is_auditd_enabled());
+        *self.capabilities.write() = Some(caps.clone());
+        caps
+    }
+    
     /// Initialize eBPF monitoring
+    /// 
+    /// Loads the CO-RE programs for every hook the kernel can attach. Fails only when no hook
+    /// can be served by eBPF (e.g. no BTF); the probe result is kept either way.
     pub fn init_ebpf(&self) -> Result<(), AgentError> {
-        info!("Initializing eBPF syscall monitoring");
+        info!("Initializing eBPF syscall monitoring (CO-RE)");
         
-        // In production, would load eBPF programs
-        // For now, mark as enabled
         self.ebpf_enabled.store(true, Ordering::Release);
+        let caps = self.reprobe();
+        if caps.count(HookSource::Ebpf) == 0 {
+            self.ebpf_enabled.store(false, Ordering::Release);
+            let reason = caps.hooks.first().and_then(|h| h.reason.clone()).unwrap_or_default();
+            self.reprobe();
+            return Err(AgentError::EbpfError(format!(
+                "no hook available on kernel {}: {}", caps.kernel_release, reason
+            )));
+        }
+        
+        // In production, would load the CO-RE object (relocated against kernel BTF) and attach
+        // one program per available hook at its probed attach point
+        for hook in caps.hooks.iter().filter(|h| h.source == HookSource::Ebpf) {
+            info!("eBPF hook {:?} attached at {}", hook.hook, hook.attach_point.as_deref().unwrap_or("-"));
+        }
+        for hook in caps.hooks.iter().filter(|h| h.source != HookSource::Ebpf) {
+            warn!("eBPF hook {:?} unavailable: {}", hook.hook, hook.reason.as_deref().unwrap_or("-"));
+        }
         
-        info!("eBPF syscall monitoring initialized");
+        info!("eBPF syscall monitoring initialized on kernel {} ({} of {} hooks, ringbuf={}, bpf_lsm={})",
+            caps.kernel_release, caps.count(HookSource::Ebpf), caps.hooks.len(), caps.ringbuf, caps.bpf_lsm);
         Ok(())
     }
     
     /// Initialize auditd monitoring (fallback)
+    /// 
+    /// Covers the hooks eBPF does not serve (all hooks with an audited syscall when eBPF is off).
     pub fn init_auditd(&self) -> Result<(), AgentError> {
         info!("Initializing auditd syscall monitoring (fallback)");
         
-        // In production, would connect to auditd
-        // For now, mark as enabled
         self.auditd_enabled.store(true, Ordering::Release);
+        let caps = self.reprobe();
         
-        info!("auditd syscall monitoring initialized");
+        // In production, would connect to auditd and install rules for these syscalls
+        let syscalls = kernel_caps::audit_syscalls(&caps);
+        
+        info!("auditd syscall monitoring initialized ({} hooks, syscalls: {})",
+            caps.count(HookSource::Auditd), syscalls.join(","));
         Ok(())
     }
     
     /// Start monitoring
+    /// 
+    /// Requires at least one hook served by eBPF or auditd; hooks neither can serve stay
+    /// reported as unavailable in the kernel capabilities.
     pub fn start(&self) -> Result<(), AgentError> {
-        // Try eBPF first
-        if let Ok(_) = self.init_ebpf() {
-            self.monitoring.store(true, Ordering::Release);
-            info!("Syscall monitoring started (eBPF)");
-            return Ok(());
+        let caps = self.capabilities().ok_or_else(|| AgentError::SyscallMonitoringFailed(
+            "Neither eBPF nor auditd initialized".to_string()
+        ))?;
+        let (ebpf, auditd, unavailable) = (
+            caps.count(HookSource::Ebpf),
+            caps.count(HookSource::Auditd),
+            caps.count(HookSource::Unavailable),
+        );
+        if ebpf + auditd == 0 {
+            return Err(AgentError::SyscallMonitoringFailed(format!(
+                "No hook available via eBPF or auditd on kernel {}", caps.kernel_release
+            )));
         }
         
-        // Fallback to auditd
-        if let Ok(_) = self.init_auditd() {
-            self.monitoring.store(true, Ordering::Release);
-            warn!("Syscall monitoring started (auditd fallback)");
-            return Ok(());
+        self.monitoring.store(true, Ordering::Release);
+        if caps.degraded() {
+            warn!("Syscall monitoring started degraded (eBPF hooks={}, auditd hooks={}, unavailable={})",
+                ebpf, auditd, unavailable);
+        } else {
+            info!("Syscall monitoring started (eBPF, all {} hooks)", ebpf);
         }
-        
-        Err(AgentError::SyscallMonitoringFailed(
-            "Failed to initialize eBPF or auditd".to_string()
-        ))
+        Ok(())
     }
     
     /// Stop monitoring
@@ -81,6 +132,11 @@ impl SyscallMonitor {
         info!("Syscall monitoring stopped");
     }
     
+    /// Kernel capability report of the last probe (None before any initialization)
+    pub fn capabilities(&self) -> Option<KernelCapabilities> {
+        self.capabilities.read().clone()
+    }
+    
     /// Check if monitoring is active
     pub fn is_monitoring(&self) -> bool {
         self.monitoring.load(Ordering::Acquire)
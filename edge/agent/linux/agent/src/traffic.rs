@@ -0,0 +1,368 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_linux_agent/agent/src/traffic.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Telemetry traffic shaping - bytes/sec bandwidth budget with burst, DSCP marking of telemetry sockets, off-peak windows for spool backlog replay
+
+/*
+ * Telemetry Traffic Shaping
+ *
+ * Telemetry must not compete with production traffic:
+ * - Bandwidth budget: every request body to /ingest/linux (live events, retries, backlog replay)
+ *   waits for its bytes in a GCRA byte bucket (rate bytes/sec, up to burst bytes at once).
+ *   Waiting never drops an event; a full send queue sheds by priority as before.
+ * - DSCP: telemetry connections are opened with IP_TOS / IPV6_TCLASS set before connect, so
+ *   every packet of the connection (handshake included) carries the code point.
+ * - Backlog windows: spooled events are replayed only inside the configured local-time windows.
+ *   Live events are always sent; outside a window the backlog simply waits in the spool.
+ */
+
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use chrono::{Local, NaiveTime, Timelike};
+use hyper::service::Service;
+use hyper::{Body, Uri};
+use hyper_tls::HttpsConnector;
+use parking_lot::Mutex;
+use reqwest::{Client, StatusCode};
+use tokio::net::{TcpSocket, TcpStream};
+
+use super::errors::AgentError;
+
+/// Highest DSCP code point (6 bits)
+pub const MAX_DSCP: u8 = 63;
+
+/// Largest 400 body kept for the rejection log (Core's validation details are far smaller)
+const MAX_REJECTION_BODY: usize = 16 * 1024;
+
+/// Byte budget for telemetry requests (generic cell rate algorithm).
+///
+/// `tat` is the theoretical time at which everything reserved so far has been sent at `rate`.
+/// A reservation may run ahead of now by at most the burst; beyond that it waits. A body larger
+/// than the burst is admitted once the bucket is full and delays the following requests.
+pub struct BandwidthBudget {
+    bytes_per_sec: u64,
+    tolerance: Duration,
+    tat: Mutex<Option<Instant>>,
+}
+
+impl BandwidthBudget {
+    /// None when `bytes_per_sec` is 0 (unlimited)
+    pub fn new(bytes_per_sec: u64, burst_bytes: u64) -> Option<Self> {
+        (bytes_per_sec > 0).then(|| Self {
+            bytes_per_sec,
+            tolerance: Duration::from_secs_f64(burst_bytes as f64 / bytes_per_sec as f64),
+            tat: Mutex::new(None),
+        })
+    }
+
+    /// Reserve `bytes` at `now`; returns how long the sender must wait before sending them
+    pub fn reserve_at(&self, bytes: usize, now: Instant) -> Duration {
+        let mut tat = self.tat.lock();
+        let start = tat.map_or(now, |t| t.max(now));
+        let next = start + Duration::from_secs_f64(bytes as f64 / self.bytes_per_sec as f64);
+        *tat = Some(next);
+        next.saturating_duration_since(now).saturating_sub(self.tolerance)
+    }
+
+    /// Wait until `bytes` fit the budget; returns the time waited
+    pub async fn acquire(&self, bytes: usize) -> Duration {
+        let wait = self.reserve_at(bytes, Instant::now());
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+        wait
+    }
+}
+
+/// One daily local-time window, `HH:MM-HH:MM`; may wrap past midnight (end exclusive)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeWindow {
+    start_min: u32,
+    end_min: u32,
+}
+
+impl TimeWindow {
+    fn contains(&self, minute: u32) -> bool {
+        if self.start_min < self.end_min {
+            (self.start_min..self.end_min).contains(&minute)
+        } else {
+            minute >= self.start_min || minute < self.end_min
+        }
+    }
+}
+
+/// Windows in which the spool backlog may be replayed; empty = any time
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BacklogWindows(Vec<TimeWindow>);
+
+impl BacklogWindows {
+    /// Parse a comma-separated list such as `22:00-06:00,12:30-13:30`; empty = any time
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut windows = Vec::new();
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (start, end) = part
+                .split_once('-')
+                .ok_or_else(|| format!("backlog window '{}' is not HH:MM-HH:MM", part))?;
+            let (start_min, end_min) = (parse_minute(start.trim(), part)?, parse_minute(end.trim(), part)?);
+            if start_min == end_min {
+                return Err(format!("backlog window '{}' is empty", part));
+            }
+            windows.push(TimeWindow { start_min, end_min });
+        }
+        Ok(Self(windows))
+    }
+
+    pub fn is_unrestricted(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Whether backlog replay is allowed at this local time of day
+    pub fn allows(&self, time: NaiveTime) -> bool {
+        let minute = time.hour() * 60 + time.minute();
+        self.is_unrestricted() || self.0.iter().any(|w| w.contains(minute))
+    }
+
+    pub fn allows_now(&self) -> bool {
+        self.allows(Local::now().time())
+    }
+}
+
+fn parse_minute(value: &str, window: &str) -> Result<u32, String> {
+    NaiveTime::parse_from_str(value, "%H:%M")
+        .map(|t| t.hour() * 60 + t.minute())
+        .map_err(|_| format!("backlog window '{}': '{}' is not HH:MM", window, value))
+}
+
+/// Connector for DSCP-marked telemetry connections: the code point is set on the socket before
+/// connect (IP_TOS for IPv4, IPV6_TCLASS for IPv6).
+#[derive(Debug, Clone, Copy)]
+pub struct DscpConnector {
+    tos: u32,
+}
+
+impl DscpConnector {
+    pub fn new(dscp: u8) -> Self {
+        // DSCP is the upper six bits of the TOS / traffic class byte; ECN bits stay 0
+        Self { tos: u32::from(dscp.min(MAX_DSCP)) << 2 }
+    }
+
+    async fn connect(tos: u32, uri: Uri) -> io::Result<TcpStream> {
+        let host = uri
+            .host()
+            .map(|h| h.trim_start_matches('[').trim_end_matches(']').to_string())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "URL has no host"))?;
+        let port = uri.port_u16().unwrap_or(if uri.scheme_str() == Some("https") { 443 } else { 80 });
+        let mut last_error = None;
+        for addr in tokio::net::lookup_host((host.as_str(), port)).await? {
+            match Self::connect_addr(tos, addr).await {
+                Ok(stream) => return Ok(stream),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} did not resolve", host))))
+    }
+
+    async fn connect_addr(tos: u32, addr: SocketAddr) -> io::Result<TcpStream> {
+        let socket = match addr {
+            SocketAddr::V4(_) => {
+                let socket = TcpSocket::new_v4()?;
+                socket.set_tos_v4(tos)?;
+                socket
+            }
+            SocketAddr::V6(_) => {
+                let socket = TcpSocket::new_v6()?;
+                socket.set_tclass_v6(tos)?;
+                socket
+            }
+        };
+        let stream = socket.connect(addr).await?;
+        stream.set_nodelay(true)?;
+        Ok(stream)
+    }
+}
+
+impl Service<Uri> for DscpConnector {
+    type Response = TcpStream;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<TcpStream>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        Box::pin(Self::connect(self.tos, uri))
+    }
+}
+
+/// HTTP client of the telemetry delivery path
+pub enum TelemetryClient {
+    /// Unmarked connections (AGENT_DSCP unset)
+    Default(Client),
+    /// Connections opened through `DscpConnector`
+    Marked {
+        client: hyper::Client<HttpsConnector<DscpConnector>, Body>,
+        timeout: Duration,
+        dscp: u8,
+    },
+}
+
+impl TelemetryClient {
+    pub fn new(dscp: Option<u8>, timeout: Duration) -> Result<Self, AgentError> {
+        match dscp {
+            None => Client::builder()
+                .timeout(timeout)
+                .build()
+                .map(TelemetryClient::Default)
+                .map_err(|e| AgentError::ConfigurationError(format!("Failed to create HTTP client: {}", e))),
+            Some(dscp) if dscp > MAX_DSCP => {
+                Err(AgentError::ConfigurationError(format!("DSCP {} out of range (0-{})", dscp, MAX_DSCP)))
+            }
+            Some(dscp) => {
+                let connector = HttpsConnector::new_with_connector(DscpConnector::new(dscp));
+                Ok(TelemetryClient::Marked { client: hyper::Client::builder().build(connector), timeout, dscp })
+            }
+        }
+    }
+
+    /// DSCP code point of telemetry connections, if marked
+    pub fn dscp(&self) -> Option<u8> {
+        match self {
+            TelemetryClient::Default(_) => None,
+            TelemetryClient::Marked { dscp, .. } => Some(*dscp),
+        }
+    }
+
+    /// POST a JSON body; the HTTP status with the body of a 400 (Core's validation details), or
+    /// the transport error
+    pub async fn post_json(
+        &self,
+        url: &str,
+        api_token: Option<&str>,
+        body: &[u8],
+    ) -> Result<(StatusCode, Option<String>), String> {
+        match self {
+            TelemetryClient::Default(client) => {
+                let mut req = client
+                    .post(url)
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+                    .body(body.to_vec());
+                if let Some(token) = api_token {
+                    req = req.bearer_auth(token);
+                }
+                let res = req.send().await.map_err(|e| e.to_string())?;
+                let status = res.status();
+                if status != StatusCode::BAD_REQUEST {
+                    return Ok((status, None));
+                }
+                Ok((status, res.bytes().await.ok().and_then(|b| rejection_body(&b))))
+            }
+            TelemetryClient::Marked { client, timeout, .. } => {
+                let mut req = hyper::Request::post(url).header(hyper::header::CONTENT_TYPE, "application/json");
+                if let Some(token) = api_token {
+                    req = req.header(hyper::header::AUTHORIZATION, format!("Bearer {}", token));
+                }
+                let req = req.body(Body::from(body.to_vec())).map_err(|e| e.to_string())?;
+                let exchange = async {
+                    let res = client.request(req).await.map_err(|e| e.to_string())?;
+                    let status = res.status();
+                    if status != StatusCode::BAD_REQUEST {
+                        return Ok((status, None));
+                    }
+                    let body = hyper::body::to_bytes(res.into_body()).await.ok();
+                    Ok((status, body.and_then(|b| rejection_body(&b))))
+                };
+                match tokio::time::timeout(*timeout, exchange).await {
+                    Ok(result) => result,
+                    Err(_) => Err(format!("no response within {:?}", timeout)),
+                }
+            }
+        }
+    }
+}
+
+fn rejection_body(bytes: &[u8]) -> Option<String> {
+    if bytes.is_empty() || bytes.len() > MAX_REJECTION_BODY {
+        return None;
+    }
+    std::str::from_utf8(bytes).ok().map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_allows_burst_then_paces() {
+        let budget = BandwidthBudget::new(1000, 2000).unwrap();
+        let now = Instant::now();
+        assert_eq!(budget.reserve_at(1000, now), Duration::ZERO);
+        assert_eq!(budget.reserve_at(1000, now), Duration::ZERO);
+        // Burst spent: the next 500 bytes wait half a second
+        assert_eq!(budget.reserve_at(500, now), Duration::from_millis(500));
+        // One second later 1000 bytes have drained, 500 are still owed
+        assert_eq!(budget.reserve_at(1000, now + Duration::from_secs(1)), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_budget_admits_oversized_body_and_refills() {
+        let budget = BandwidthBudget::new(1000, 1000).unwrap();
+        let now = Instant::now();
+        assert_eq!(budget.reserve_at(5000, now), Duration::from_secs(4));
+        // Idle long enough: back to a full burst
+        assert_eq!(budget.reserve_at(1000, now + Duration::from_secs(10)), Duration::ZERO);
+        assert!(BandwidthBudget::new(0, 1000).is_none());
+    }
+
+    #[test]
+    fn test_backlog_windows() {
+        let at = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        let any = BacklogWindows::parse("").unwrap();
+        assert!(any.is_unrestricted() && any.allows(at(14, 0)));
+
+        let windows = BacklogWindows::parse("22:00-06:00, 12:30-13:30").unwrap();
+        assert!(windows.allows(at(23, 15)));
+        assert!(windows.allows(at(0, 0)));
+        assert!(windows.allows(at(12, 30)));
+        assert!(!windows.allows(at(6, 0)));
+        assert!(!windows.allows(at(13, 30)));
+        assert!(!windows.allows(at(9, 0)));
+
+        assert!(BacklogWindows::parse("22:00").is_err());
+        assert!(BacklogWindows::parse("25:00-06:00").is_err());
+        assert!(BacklogWindows::parse("06:00-06:00").is_err());
+    }
+
+    #[test]
+    fn test_dscp_shifts_into_tos_byte() {
+        assert_eq!(DscpConnector::new(8).tos, 0x20);
+        assert_eq!(DscpConnector::new(46).tos, 0xb8);
+        assert!(TelemetryClient::new(Some(64), Duration::from_secs(1)).is_err());
+        assert_eq!(TelemetryClient::new(Some(8), Duration::from_secs(1)).unwrap().dscp(), Some(8));
+        assert_eq!(TelemetryClient::new(None, Duration::from_secs(1)).unwrap().dscp(), None);
+    }
+
+    #[tokio::test]
+    async fn test_marked_connection_carries_dscp() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let accept = tokio::spawn(async move { listener.accept().await.unwrap() });
+        let stream = DscpConnector::connect(DscpConnector::new(10).tos, format!("http://{}/", addr).parse().unwrap())
+            .await
+            .unwrap();
+        accept.await.unwrap();
+
+        use std::os::unix::io::AsRawFd;
+        let mut tos: libc::c_int = 0;
+        let mut len = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+        let rc = unsafe {
+            libc::getsockopt(stream.as_raw_fd(), libc::IPPROTO_IP, libc::IP_TOS, &mut tos as *mut _ as *mut libc::c_void, &mut len)
+        };
+        assert_eq!(rc, 0);
+        assert_eq!(tos, 10 << 2);
+    }
+}
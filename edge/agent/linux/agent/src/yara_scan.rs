@@ -0,0 +1,696 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_linux_agent/agent/src/yara_scan.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Scheduled and on-demand YARA scanning - signed rule packs from Core verified against pinned keys, CPU-throttled scans with the external yara tool, match events and scan summaries
+
+/*
+ * YARA Scanning
+ *
+ * Off unless AGENT_YARA_SCANNING is set. Rules come from Core as a signed pack (see
+ * core/ingest/src/yara_rules.rs): the agent polls POST /agents/yara/poll with the version it
+ * runs and installs a newer pack only if its Ed25519 signature verifies against a key pinned on
+ * this host (AGENT_YARA_SIGNERS) and its version is higher than the installed one. Core cannot
+ * change a rule or roll a pack back. The installed pack is kept in the work directory and
+ * verified again at startup.
+ *
+ * Scans run when Core hands out an operator's on-demand scan, and every
+ * AGENT_YARA_SCAN_INTERVAL_SECS over AGENT_YARA_SCAN_PATHS. The agent walks the paths itself
+ * (no symlinks, regular files up to AGENT_YARA_MAX_FILE_MB, never its own work directory) and
+ * runs the yara tool without a shell on BATCH_FILES files at a time:
+ *
+ *   yara -w -m -p 1 -a <timeout> --scan-list <rules> <file list>
+ *
+ * CPU use is bounded by a duty cycle: after a batch that took t, the scan pauses for
+ * t * (100 - AGENT_YARA_CPU_PERCENT) / AGENT_YARA_CPU_PERCENT. Each match becomes a yara_match
+ * event (high severity, delivered at critical priority) with the rule name and meta, the file's
+ * path, size and SHA-256, and the pack version. The summary of the last scan goes out with
+ * agent_stats.
+ */
+
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chrono::{DateTime, Utc};
+use crypto::digest::Sha256;
+use crypto::signature::{verify_ed25519, ED25519_PUBLIC_KEY_LEN};
+use reqwest::Client as ReqwestClient;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use super::errors::AgentError;
+use super::pipeline::ShutdownSignal;
+
+/// Files handed to one yara run
+pub const BATCH_FILES: usize = 256;
+/// Meta entries and meta value length kept per match
+const MAX_META_ENTRIES: usize = 32;
+const MAX_META_VALUE_CHARS: usize = 1024;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+/// Installed pack (as received, re-verified at startup) and the last scheduled scan
+const PACK_FILE: &str = "pack.json";
+const SCHEDULE_FILE: &str = "last_scheduled";
+const SCAN_LIST_FILE: &str = "scan-list";
+
+/// Pack handed out by POST /agents/yara/poll, exactly as signed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RulePackOrder {
+    pub pack_id: Uuid,
+    pub version: i64,
+    pub document: String,
+    /// Base64 Ed25519 signature over `document`
+    pub signature: String,
+    /// Hex Ed25519 public key of the signer
+    pub signer_key: String,
+}
+
+/// On-demand scan handed out by POST /agents/yara/poll
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScanOrder {
+    pub scan_id: Uuid,
+    /// Empty: AGENT_YARA_SCAN_PATHS
+    #[serde(default)]
+    pub paths: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PollResponse {
+    #[serde(default)]
+    pack: Option<RulePackOrder>,
+    #[serde(default)]
+    scan: Option<ScanOrder>,
+}
+
+/// Signed pack document (fields the agent uses)
+#[derive(Debug, Clone, Deserialize)]
+pub struct RulePack {
+    pub name: String,
+    pub version: i64,
+    pub rules: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanKind {
+    Scheduled,
+    OnDemand,
+}
+
+impl ScanKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScanKind::Scheduled => "scheduled",
+            ScanKind::OnDemand => "on_demand",
+        }
+    }
+}
+
+/// One rule match as printed by `yara -m`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct YaraHit {
+    pub rule: String,
+    pub meta: BTreeMap<String, String>,
+    pub path: String,
+}
+
+/// yara_match event payload
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct YaraMatchData {
+    pub rule: String,
+    /// Rule meta (author, description, reference, ...)
+    #[serde(default)]
+    pub meta: BTreeMap<String, String>,
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_sha256: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_size: Option<u64>,
+    pub pack_name: String,
+    pub pack_version: i64,
+    /// scheduled or on_demand
+    pub scan_kind: String,
+    /// Operator request the scan ran for (on-demand scans)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scan_id: Option<Uuid>,
+    pub severity: String,
+}
+
+/// Outcome of one scan (agent_stats)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct YaraScanSummary {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scan_id: Option<Uuid>,
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pack_version: Option<i64>,
+    pub started_at: String,
+    pub duration_ms: u64,
+    pub files_scanned: u64,
+    /// Too large, unreadable, or not listable for yara
+    pub files_skipped: u64,
+    pub bytes_scanned: u64,
+    pub matches: u64,
+    pub errors: u64,
+    /// Time paused by the CPU throttle
+    pub throttled_ms: u64,
+    /// False when the scan was cut short (shutdown, no rule pack)
+    pub completed: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<String>,
+}
+
+/// Installed pack and last scan (agent_stats)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct YaraScanStatus {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pack_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pack_version: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_scan: Option<YaraScanSummary>,
+}
+
+#[derive(Debug, Clone)]
+pub struct YaraScanConfig {
+    /// Absolute path of the yara binary
+    pub command: PathBuf,
+    /// Keys trusted to sign rule packs
+    pub signers: Vec<[u8; ED25519_PUBLIC_KEY_LEN]>,
+    pub scan_paths: Vec<PathBuf>,
+    /// Scheduled scans (zero: on-demand only)
+    pub scan_interval: Duration,
+    /// Share of one CPU the scan may use (1-100)
+    pub cpu_percent: u32,
+    pub max_file_bytes: u64,
+    pub work_dir: PathBuf,
+    pub poll_interval: Duration,
+    /// yara timeout per file
+    pub file_timeout: Duration,
+}
+
+/// Comma-separated hex Ed25519 public keys (AGENT_YARA_SIGNERS).
+pub fn parse_signers(list: &str) -> Result<Vec<[u8; ED25519_PUBLIC_KEY_LEN]>, AgentError> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|e| !e.is_empty())
+        .map(|entry| {
+            hex::decode(entry)
+                .ok()
+                .and_then(|b| b.try_into().ok())
+                .ok_or_else(|| AgentError::ConfigurationError(format!("AGENT_YARA_SIGNERS: '{}' is not a hex Ed25519 public key", entry)))
+        })
+        .collect()
+}
+
+/// The pack in `order`, if a pinned key signed it and it is newer than `installed`.
+pub fn verify_pack(
+    signers: &[[u8; ED25519_PUBLIC_KEY_LEN]],
+    order: &RulePackOrder,
+    installed: Option<i64>,
+) -> Result<RulePack, AgentError> {
+    let refuse = |reason: String| AgentError::YaraScanFailed(format!("rule pack {} refused: {}", order.version, reason));
+    let signer_key = hex::decode(order.signer_key.trim()).map_err(|_| refuse("signer key is not hex".to_string()))?;
+    if !signers.iter().any(|k| k.as_slice() == signer_key.as_slice()) {
+        return Err(refuse(format!("signer {} is not pinned on this host", order.signer_key)));
+    }
+    let signature = STANDARD.decode(order.signature.trim()).map_err(|_| refuse("signature is not base64".to_string()))?;
+    verify_ed25519(&signer_key, order.document.as_bytes(), &signature)
+        .map_err(|_| refuse("signature does not verify".to_string()))?;
+    let pack: RulePack = serde_json::from_str(&order.document).map_err(|e| refuse(format!("invalid document: {}", e)))?;
+    if pack.version != order.version {
+        return Err(refuse(format!("document is version {}", pack.version)));
+    }
+    if installed.is_some_and(|v| pack.version <= v) {
+        return Err(refuse(format!("not newer than installed version {}", installed.unwrap_or_default())));
+    }
+    Ok(pack)
+}
+
+/// Key/value pairs inside the brackets of `yara -m` output.
+fn parse_meta(text: &str) -> BTreeMap<String, String> {
+    let mut meta = BTreeMap::new();
+    let (mut key, mut value) = (String::new(), String::new());
+    let (mut in_value, mut in_quotes, mut escaped) = (false, false, false);
+    let mut insert = |key: &mut String, value: &mut String| {
+        let k = key.trim();
+        if !k.is_empty() && meta.len() < MAX_META_ENTRIES {
+            meta.insert(k.to_string(), value.chars().take(MAX_META_VALUE_CHARS).collect());
+        }
+        key.clear();
+        value.clear();
+    };
+    for c in text.chars() {
+        if escaped {
+            value.push(c);
+            escaped = false;
+            continue;
+        }
+        match c {
+            '\\' if in_quotes => escaped = true,
+            '"' if in_value => in_quotes = !in_quotes,
+            '=' if !in_value => in_value = true,
+            ',' if !in_quotes => {
+                insert(&mut key, &mut value);
+                in_value = false;
+            }
+            _ if in_value => value.push(c),
+            _ => key.push(c),
+        }
+    }
+    insert(&mut key, &mut value);
+    meta
+}
+
+/// One line of `yara -m` output: `<rule> [<meta>] <path>`.
+pub fn parse_yara_line(line: &str) -> Option<YaraHit> {
+    let (rule, rest) = line.split_once(' ')?;
+    if rule.is_empty() {
+        return None;
+    }
+    let (meta, path) = match rest.strip_prefix('[') {
+        Some(rest) => {
+            let (mut in_quotes, mut escaped, mut end) = (false, false, None);
+            for (i, c) in rest.char_indices() {
+                if escaped {
+                    escaped = false;
+                    continue;
+                }
+                match c {
+                    '\\' if in_quotes => escaped = true,
+                    '"' => in_quotes = !in_quotes,
+                    ']' if !in_quotes => {
+                        end = Some(i);
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            let end = end?;
+            (parse_meta(&rest[..end]), rest[end + 1..].strip_prefix(' ')?)
+        }
+        None => (BTreeMap::new(), rest),
+    };
+    if path.is_empty() {
+        return None;
+    }
+    Some(YaraHit { rule: rule.to_string(), meta, path: path.to_string() })
+}
+
+/// Regular files under `roots` (symlinks not followed) up to `max_file_bytes`, outside
+/// `exclude`; returns the files with their sizes and the number skipped.
+pub fn collect_files(roots: &[PathBuf], exclude: &Path, max_file_bytes: u64) -> (Vec<(PathBuf, u64)>, u64) {
+    let mut files = Vec::new();
+    let mut skipped = 0u64;
+    let mut stack: Vec<PathBuf> = roots.to_vec();
+    while let Some(path) = stack.pop() {
+        if path.starts_with(exclude) {
+            continue;
+        }
+        let Ok(meta) = std::fs::symlink_metadata(&path) else {
+            skipped += 1;
+            continue;
+        };
+        if meta.is_dir() {
+            match std::fs::read_dir(&path) {
+                Ok(entries) => stack.extend(entries.filter_map(|e| e.ok().map(|e| e.path()))),
+                Err(_) => skipped += 1,
+            }
+        } else if meta.is_file() {
+            // The scan list is one path per line
+            if meta.len() > max_file_bytes || path.to_string_lossy().contains('\n') {
+                skipped += 1;
+            } else {
+                files.push((path, meta.len()));
+            }
+        }
+    }
+    files.sort();
+    (files, skipped)
+}
+
+fn file_sha256(path: &Path) -> std::io::Result<(String, u64)> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut size = 0u64;
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        size += n as u64;
+    }
+    Ok((hex::encode(hasher.finalize()), size))
+}
+
+/// Pause after a batch that took `busy` so the scan stays within `cpu_percent` of one CPU.
+pub fn throttle_pause(busy: Duration, cpu_percent: u32) -> Duration {
+    let pct = cpu_percent.clamp(1, 100);
+    busy * (100 - pct) / pct
+}
+
+#[derive(Debug, Clone)]
+struct InstalledPack {
+    name: String,
+    version: i64,
+    rules_path: PathBuf,
+}
+
+#[derive(Debug, Default)]
+struct BatchOutput {
+    hits: Vec<YaraHit>,
+    errors: u64,
+}
+
+/// YARA scanner (one scan at a time)
+pub struct YaraScanner {
+    client: ReqwestClient,
+    core_api_url: String,
+    api_token: String,
+    config: YaraScanConfig,
+    pack: Option<InstalledPack>,
+    last_scan: Option<YaraScanSummary>,
+}
+
+impl YaraScanner {
+    pub fn new(core_api_url: String, api_token: String, config: YaraScanConfig) -> Result<Self, AgentError> {
+        std::fs::create_dir_all(&config.work_dir).map_err(|e| {
+            AgentError::ConfigurationError(format!("Failed to create YARA work directory {}: {}", config.work_dir.display(), e))
+        })?;
+        let client = ReqwestClient::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| AgentError::ConfigurationError(format!("Failed to create HTTP client: {}", e)))?;
+        let mut scanner = Self { client, core_api_url, api_token, config, pack: None, last_scan: None };
+        // A stored pack is only used if it still verifies; otherwise Core hands it out again
+        let stored = std::fs::read(scanner.config.work_dir.join(PACK_FILE))
+            .ok()
+            .and_then(|b| serde_json::from_slice::<RulePackOrder>(&b).ok());
+        if let Some(order) = stored {
+            if let Err(e) = scanner.install(&order) {
+                warn!("Stored YARA rule pack not used: {}", e);
+            }
+        }
+        Ok(scanner)
+    }
+
+    pub fn poll_interval(&self) -> Duration {
+        self.config.poll_interval
+    }
+
+    pub fn status(&self) -> YaraScanStatus {
+        YaraScanStatus {
+            pack_name: self.pack.as_ref().map(|p| p.name.clone()),
+            pack_version: self.pack.as_ref().map(|p| p.version),
+            last_scan: self.last_scan.clone(),
+        }
+    }
+
+    /// Verify a pack and make it the one scans use.
+    fn install(&mut self, order: &RulePackOrder) -> Result<(), AgentError> {
+        let pack = verify_pack(&self.config.signers, order, self.pack.as_ref().map(|p| p.version))?;
+        let io = |e: std::io::Error| AgentError::YaraScanFailed(format!("rule pack {}: {}", pack.version, e));
+        let rules_path = self.config.work_dir.join(format!("rules-{}.yar", pack.version));
+        let tmp = self.config.work_dir.join(format!("rules-{}.yar.tmp", pack.version));
+        std::fs::write(&tmp, pack.rules.as_bytes()).map_err(io)?;
+        std::fs::rename(&tmp, &rules_path).map_err(io)?;
+        let order_json = serde_json::to_vec(order).map_err(|e| AgentError::YaraScanFailed(e.to_string()))?;
+        std::fs::write(self.config.work_dir.join(PACK_FILE), order_json).map_err(io)?;
+        if let Some(old) = self.pack.replace(InstalledPack { name: pack.name.clone(), version: pack.version, rules_path }) {
+            let _ = std::fs::remove_file(old.rules_path);
+        }
+        info!("YARA rule pack installed | name={} | version={} | rules_bytes={}", pack.name, pack.version, pack.rules.len());
+        Ok(())
+    }
+
+    /// Ask Core for a newer pack (installed if it verifies) and this agent's pending scan.
+    pub async fn poll(&mut self) -> Result<Option<ScanOrder>, AgentError> {
+        let res = self
+            .client
+            .post(format!("{}/agents/yara/poll", self.core_api_url))
+            .bearer_auth(&self.api_token)
+            .json(&serde_json::json!({"pack_version": self.pack.as_ref().map(|p| p.version)}))
+            .send()
+            .await
+            .map_err(|e| AgentError::YaraScanFailed(format!("poll: {}", e)))?;
+        if !res.status().is_success() {
+            return Err(AgentError::YaraScanFailed(format!("poll: HTTP {}", res.status())));
+        }
+        let answer: PollResponse =
+            res.json().await.map_err(|e| AgentError::YaraScanFailed(format!("poll: invalid answer: {}", e)))?;
+        if let Some(order) = answer.pack {
+            // A refused pack leaves the installed one in use
+            if let Err(e) = self.install(&order) {
+                error!("{}", e);
+            }
+        }
+        Ok(answer.scan)
+    }
+
+    /// A scheduled scan is due: a pack is installed and the interval passed since the last one.
+    pub fn scheduled_due(&self) -> bool {
+        if self.config.scan_interval.is_zero() || self.pack.is_none() {
+            return false;
+        }
+        let last = std::fs::read_to_string(self.config.work_dir.join(SCHEDULE_FILE))
+            .ok()
+            .and_then(|s| DateTime::parse_from_rfc3339(s.trim()).ok());
+        last.is_none_or(|at| {
+            Utc::now().signed_duration_since(at).to_std().is_ok_and(|elapsed| elapsed >= self.config.scan_interval)
+        })
+    }
+
+    /// Scan `paths` (empty: the configured scan paths), sending one match event per hit.
+    pub async fn scan<T: From<YaraMatchData>>(
+        &mut self,
+        kind: ScanKind,
+        scan_id: Option<Uuid>,
+        paths: &[String],
+        shutdown: &ShutdownSignal,
+        matches: &mpsc::Sender<T>,
+    ) -> YaraScanSummary {
+        let started = Instant::now();
+        let mut summary = YaraScanSummary {
+            scan_id,
+            kind: kind.as_str().to_string(),
+            pack_version: self.pack.as_ref().map(|p| p.version),
+            started_at: Utc::now().to_rfc3339(),
+            ..Default::default()
+        };
+        match self.pack.clone() {
+            Some(pack) => self.run_scan(&pack, kind, paths, shutdown, matches, &mut summary).await,
+            None => summary.failure = Some("no rule pack installed".to_string()),
+        }
+        summary.duration_ms = started.elapsed().as_millis() as u64;
+        if kind == ScanKind::Scheduled && summary.completed {
+            let _ = std::fs::write(self.config.work_dir.join(SCHEDULE_FILE), Utc::now().to_rfc3339());
+        }
+        info!(
+            "YARA {} scan finished | scan_id={:?} | files={} | skipped={} | matches={} | errors={} | throttled_ms={} | completed={}",
+            summary.kind, scan_id, summary.files_scanned, summary.files_skipped, summary.matches, summary.errors,
+            summary.throttled_ms, summary.completed
+        );
+        self.last_scan = Some(summary.clone());
+        summary
+    }
+
+    async fn run_scan<T: From<YaraMatchData>>(
+        &self,
+        pack: &InstalledPack,
+        kind: ScanKind,
+        paths: &[String],
+        shutdown: &ShutdownSignal,
+        matches: &mpsc::Sender<T>,
+        summary: &mut YaraScanSummary,
+    ) {
+        let roots: Vec<PathBuf> = if paths.is_empty() {
+            self.config.scan_paths.clone()
+        } else {
+            paths.iter().map(PathBuf::from).collect()
+        };
+        let (work_dir, max_file_bytes) = (self.config.work_dir.clone(), self.config.max_file_bytes);
+        let (files, skipped) = match tokio::task::spawn_blocking(move || collect_files(&roots, &work_dir, max_file_bytes)).await {
+            Ok(found) => found,
+            Err(e) => {
+                summary.failure = Some(format!("file walk: {}", e));
+                return;
+            }
+        };
+        summary.files_skipped = skipped;
+        info!("YARA {} scan started | pack={} | files={} | skipped={}", kind.as_str(), pack.version, files.len(), skipped);
+
+        for batch in files.chunks(BATCH_FILES) {
+            if shutdown.is_triggered() {
+                summary.failure = Some("agent shutting down".to_string());
+                return;
+            }
+            let busy = Instant::now();
+            match self.run_batch(pack, batch).await {
+                Ok(output) => {
+                    summary.errors += output.errors;
+                    for hit in output.hits {
+                        let data = self.match_data(hit, pack, kind, summary.scan_id).await;
+                        warn!("YARA match | rule={} | path={} | pack={}", data.rule, data.path, pack.version);
+                        summary.matches += 1;
+                        if matches.send(T::from(data)).await.is_err() {
+                            summary.failure = Some("event pipeline closed".to_string());
+                            return;
+                        }
+                    }
+                }
+                Err(e) => {
+                    warn!("YARA batch failed: {}", e);
+                    summary.errors += 1;
+                }
+            }
+            summary.files_scanned += batch.len() as u64;
+            summary.bytes_scanned += batch.iter().map(|(_, size)| size).sum::<u64>();
+
+            let pause = throttle_pause(busy.elapsed(), self.config.cpu_percent);
+            summary.throttled_ms += pause.as_millis() as u64;
+            tokio::select! {
+                _ = shutdown.wait() => {}
+                _ = tokio::time::sleep(pause) => {}
+            }
+        }
+        summary.completed = true;
+    }
+
+    async fn run_batch(&self, pack: &InstalledPack, batch: &[(PathBuf, u64)]) -> Result<BatchOutput, AgentError> {
+        let failed = |e: String| AgentError::YaraScanFailed(e);
+        let list_path = self.config.work_dir.join(SCAN_LIST_FILE);
+        let list: String = batch.iter().map(|(p, _)| format!("{}\n", p.to_string_lossy())).collect();
+        tokio::fs::write(&list_path, list).await.map_err(|e| failed(format!("scan list: {}", e)))?;
+
+        let timeout_secs = self.config.file_timeout.as_secs().max(1);
+        let child = tokio::process::Command::new(&self.config.command)
+            .args(["-w", "-m", "-p", "1", "-a", &timeout_secs.to_string(), "--scan-list"])
+            .arg(&pack.rules_path)
+            .arg(&list_path)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| failed(format!("failed to start {}: {}", self.config.command.display(), e)))?;
+        let limit = self.config.file_timeout * (batch.len() as u32 + 1);
+        let output = tokio::time::timeout(limit, child.wait_with_output())
+            .await
+            .map_err(|_| failed(format!("yara timed out after {:?}", limit)))?
+            .map_err(|e| failed(format!("yara: {}", e)))?;
+
+        let hits: Vec<YaraHit> = String::from_utf8_lossy(&output.stdout).lines().filter_map(parse_yara_line).collect();
+        let mut errors = String::from_utf8_lossy(&output.stderr).lines().filter(|l| !l.trim().is_empty()).count() as u64;
+        if !output.status.success() && errors == 0 {
+            errors = 1;
+        }
+        Ok(BatchOutput { hits, errors })
+    }
+
+    async fn match_data(&self, hit: YaraHit, pack: &InstalledPack, kind: ScanKind, scan_id: Option<Uuid>) -> YaraMatchData {
+        let path = PathBuf::from(&hit.path);
+        let hashed = tokio::task::spawn_blocking(move || file_sha256(&path)).await.ok().and_then(Result::ok);
+        YaraMatchData {
+            rule: hit.rule,
+            meta: hit.meta,
+            path: hit.path,
+            file_sha256: hashed.as_ref().map(|(sha256, _)| sha256.clone()),
+            file_size: hashed.map(|(_, size)| size),
+            pack_name: pack.name.clone(),
+            pack_version: pack.version,
+            scan_kind: kind.as_str().to_string(),
+            scan_id,
+            severity: "high".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crypto::signature::Ed25519KeyPair;
+
+    fn order(key: &Ed25519KeyPair, version: i64) -> RulePackOrder {
+        let document = serde_json::json!({
+            "name": "ransomware-families",
+            "version": version,
+            "rules": "rule LockBit_Note { strings: $a = \"Restore-My-Files.txt\" condition: $a }",
+            "reason": "CHG-2211",
+            "created_by": "threat-intel",
+        })
+        .to_string();
+        RulePackOrder {
+            pack_id: Uuid::new_v4(),
+            version,
+            signature: STANDARD.encode(key.sign(document.as_bytes())),
+            signer_key: hex::encode(key.public_key()),
+            document,
+        }
+    }
+
+    #[test]
+    fn test_packs_verify_against_pinned_keys_only() {
+        let key = Ed25519KeyPair::generate().unwrap();
+        let stranger = Ed25519KeyPair::generate().unwrap();
+        let signers = parse_signers(&format!("{}, ", hex::encode(key.public_key()))).unwrap();
+        assert!(parse_signers("00ff").is_err());
+
+        let pack = verify_pack(&signers, &order(&key, 7), None).unwrap();
+        assert_eq!((pack.name.as_str(), pack.version), ("ransomware-families", 7));
+        // No rollback, no foreign signer, no altered rules
+        assert!(verify_pack(&signers, &order(&key, 7), Some(7)).is_err());
+        assert!(verify_pack(&signers, &order(&key, 6), Some(7)).is_err());
+        assert!(verify_pack(&signers, &order(&stranger, 8), Some(7)).is_err());
+        let mut altered = order(&key, 8);
+        altered.document = altered.document.replace("Restore-My-Files", "Restore-Your-Files");
+        assert!(verify_pack(&signers, &altered, Some(7)).is_err());
+        let mut relabelled = order(&key, 8);
+        relabelled.version = 9;
+        assert!(verify_pack(&signers, &relabelled, Some(7)).is_err());
+    }
+
+    #[test]
+    fn test_yara_output_lines() {
+        let hit = parse_yara_line(
+            r#"LockBit_Note [author="intel, team",description="Ransom note \"Restore\"",score=90] /home/a/Restore-My-Files.txt"#,
+        )
+        .unwrap();
+        assert_eq!(hit.rule, "LockBit_Note");
+        assert_eq!(hit.meta["author"], "intel, team");
+        assert_eq!(hit.meta["description"], r#"Ransom note "Restore""#);
+        assert_eq!(hit.meta["score"], "90");
+        assert_eq!(hit.path, "/home/a/Restore-My-Files.txt");
+
+        let bare = parse_yara_line("Generic_Packer [] /tmp/with space/x.bin").unwrap();
+        assert!(bare.meta.is_empty());
+        assert_eq!(bare.path, "/tmp/with space/x.bin");
+        assert_eq!(parse_yara_line("Generic_Packer /tmp/x.bin").unwrap().path, "/tmp/x.bin");
+        assert!(parse_yara_line("").is_none());
+        assert!(parse_yara_line("Rule [author=\"unterminated] /x").is_none());
+    }
+
+    #[test]
+    fn test_file_walk_and_throttle() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let work = dir.path().join("work");
+        std::fs::create_dir_all(dir.path().join("data/nested")).unwrap();
+        std::fs::create_dir_all(&work).unwrap();
+        std::fs::write(dir.path().join("data/a.bin"), b"abc").unwrap();
+        std::fs::write(dir.path().join("data/nested/b.bin"), b"defg").unwrap();
+        std::fs::write(dir.path().join("data/big.bin"), vec![0u8; 64]).unwrap();
+        std::fs::write(work.join("rules-1.yar"), b"rule x { condition: true }").unwrap();
+        std::os::unix::fs::symlink("/etc", dir.path().join("data/link")).unwrap();
+
+        let (files, skipped) = collect_files(&[dir.path().to_path_buf()], &work, 16);
+        let names: Vec<_> = files.iter().map(|(p, size)| (p.file_name().unwrap().to_str().unwrap(), *size)).collect();
+        assert_eq!(names, vec![("a.bin", 3), ("b.bin", 4)]);
+        assert_eq!(skipped, 1, "the oversized file");
+
+        assert_eq!(throttle_pause(Duration::from_millis(200), 20), Duration::from_millis(800));
+        assert_eq!(throttle_pause(Duration::from_millis(200), 100), Duration::ZERO);
+        assert_eq!(throttle_pause(Duration::from_millis(10), 0), Duration::from_millis(990));
+    }
+}
@@ -0,0 +1,92 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_linux_agent/benches/signing_throughput.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Event signing throughput - inline on the async runtime versus the signing pool at 1, 2 and 4 workers
+
+/*
+ * Per event this does what the agent does: serialize the envelope, SHA-256 of the canonical
+ * bytes, an Ed25519 signature over the event data and one over the hash. On constrained
+ * hardware, pin the run to the cores the agent gets, e.g.
+ *   taskset -c 0,1 cargo bench -p agent-linux --bench signing_throughput
+ */
+
+use agent_linux::envelope::payload_digest;
+use agent_linux::signing_pool;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use crypto::signature::Ed25519KeyPair;
+use std::sync::Arc;
+
+const EVENTS: u64 = 512;
+
+fn sample_event(i: u64) -> serde_json::Value {
+    serde_json::json!({
+        "event_id": format!("3f6c1d0e-8a2b-4c5d-9e7f-{:012}", i),
+        "timestamp": "2026-10-16T08:00:00.000000Z",
+        "component": "linux_agent",
+        "component_id": "a3b9c1d2-0000-4000-8000-000000000001",
+        "event_type": "process_exec",
+        "sequence": i,
+        "signature": "",
+        "data": {
+            "event_category": "process",
+            "pid": 4242 + i,
+            "uid": 1000,
+            "gid": 1000,
+            "process_data": {
+                "event_type": "exec",
+                "ppid": 1,
+                "executable": "/usr/bin/python3",
+                "command_line": "/usr/bin/python3 /opt/app/worker.py --queue ingest --concurrency 8",
+            },
+            "features": { "feature_count": 12, "path_count": 3, "paths": ["/opt/app", "/tmp", "/var/log/app"] },
+        }
+    })
+}
+
+/// One event's hashing and signing; returns the delivery signature
+fn sign_event(key: &Ed25519KeyPair, event: &serde_json::Value) -> [u8; 64] {
+    let payload = serde_json::to_vec(&event["data"]).unwrap();
+    let _ = key.sign(&payload);
+    let canonical = serde_json::to_vec(event).unwrap();
+    key.sign(&payload_digest(&canonical))
+}
+
+fn bench_signing(c: &mut Criterion) {
+    let key = Arc::new(Ed25519KeyPair::from_seed(&[7u8; 32]).unwrap());
+    let events: Vec<serde_json::Value> = (0..EVENTS).map(sample_event).collect();
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+
+    let mut group = c.benchmark_group("event_signing");
+    group.throughput(Throughput::Elements(EVENTS));
+    group.bench_function("inline", |b| {
+        b.iter(|| {
+            for event in &events {
+                criterion::black_box(sign_event(&key, event));
+            }
+        })
+    });
+    for workers in [1usize, 2, 4] {
+        group.bench_with_input(BenchmarkId::new("pool", workers), &workers, |b, &workers| {
+            b.iter(|| {
+                let key = key.clone();
+                let (mut submitter, mut results) =
+                    signing_pool::start(workers, 1024, move |event: serde_json::Value| sign_event(&key, &event));
+                runtime.block_on(async {
+                    let events = events.clone();
+                    let producer = tokio::spawn(async move {
+                        for event in events {
+                            submitter.submit(event).await;
+                        }
+                    });
+                    while let Some(signature) = results.next().await {
+                        criterion::black_box(signature);
+                    }
+                    producer.await.unwrap();
+                })
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_signing);
+criterion_main!(benches);
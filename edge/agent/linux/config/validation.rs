@@ -12,6 +12,8 @@ pub struct AgentConfig {
     pub max_processes: usize,
     pub max_connections: usize,
     pub max_queue_size: usize,
+    /// Threads hashing and signing events off the async runtime
+    pub signing_workers: usize,
     pub rate_limit_tokens: u64,
     pub rate_limit_refill: u64,
     pub mass_write_threshold: u64,
@@ -20,6 +22,163 @@ pub struct AgentConfig {
     pub enable_ebpf: bool,
     pub enable_auditd: bool,
     pub core_api_url: String,
+    /// File holding the per-agent ingest bearer token issued at enrollment (optional until mTLS)
+    pub api_token_path: Option<String>,
+    /// Delivery attempts per event including the first
+    pub delivery_max_attempts: u32,
+    pub delivery_backoff_base_ms: u64,
+    pub delivery_backoff_max_ms: u64,
+    /// Retry budget (token bucket): burst size and refill per second
+    pub delivery_retry_budget: u64,
+    pub delivery_retry_refill: u64,
+    /// Consecutive delivery failures before the circuit breaker opens
+    pub circuit_failure_threshold: u32,
+    pub circuit_open_secs: u64,
+    /// Telemetry bandwidth cap in bytes/sec (0 = unlimited) and the burst allowed above it
+    pub bandwidth_bytes_per_sec: u64,
+    pub bandwidth_burst_bytes: u64,
+    /// DSCP code point (0-63) for telemetry connections; None = unmarked
+    pub dscp: Option<u8>,
+    /// Local-time windows for spool backlog replay, e.g. "22:00-06:00" (empty = any time)
+    pub backlog_windows: String,
+    /// Local spool for events that could not be delivered
+    pub spool_dir: String,
+    pub spool_max_mb: u64,
+    /// Uncompressed size at which the active spool segment is rotated and compressed
+    pub spool_segment_kb: u64,
+    /// Global budget for everything the agent writes locally (spool + managed logs)
+    pub disk_budget_mb: u64,
+    /// Host filesystem root for /proc lookups (cgroup, network namespace) and the Docker container configs
+    /// ("/" on the host, e.g. "/host" when the agent itself runs in a container)
+    pub host_root: String,
+    /// Kubelet pod list (e.g. https://127.0.0.1:10250/pods); unset = no pod metadata
+    pub kubelet_url: Option<String>,
+    pub kubelet_token_path: Option<String>,
+    pub kubelet_ca_path: Option<String>,
+    pub kubelet_refresh_secs: u64,
+    /// Take operator-requested memory images (never automatic; Core must also allow it)
+    pub memory_acquisition: bool,
+    /// Full-memory acquisition command template ({output}), e.g. "/usr/bin/avml {output}"; unset = refused
+    pub memory_full_command: Option<String>,
+    /// Process acquisition command template ({pid}, {output}); unset = refused
+    pub memory_process_command: Option<String>,
+    /// Where the tool writes the image until it has been streamed to Core
+    pub memory_work_dir: String,
+    pub memory_poll_secs: u64,
+    /// The tool is killed (and the acquisition failed) after this long
+    pub memory_tool_timeout_secs: u64,
+    /// Scan files with YARA rule packs signed by AGENT_YARA_SIGNERS and handed out by Core
+    pub yara_scanning: bool,
+    /// Absolute path of the yara binary
+    pub yara_command: String,
+    /// Comma-separated hex Ed25519 keys trusted to sign rule packs
+    pub yara_signers: String,
+    /// Paths of scheduled scans (and of on-demand scans naming none)
+    pub yara_scan_paths: Vec<String>,
+    /// Scheduled scan interval; 0 = on-demand scans only
+    pub yara_scan_interval_secs: u64,
+    /// Share of one CPU a scan may use (1-100)
+    pub yara_cpu_percent: u32,
+    /// Larger files are skipped
+    pub yara_max_file_mb: u64,
+    /// Installed rule pack and scan lists
+    pub yara_work_dir: String,
+    pub yara_poll_secs: u64,
+    /// yara timeout per file
+    pub yara_file_timeout_secs: u64,
+    /// Signed binary hash allowlist/blocklist file (None = no hash policy)
+    pub hash_policy_path: Option<String>,
+    /// Comma-separated hex Ed25519 keys trusted to sign the hash policy
+    pub hash_policy_signers: String,
+    /// Kill processes running a blocklisted binary
+    pub hash_policy_enforce: bool,
+    /// How often the policy file is checked for changes
+    pub hash_policy_reload_secs: u64,
+    /// Larger binaries are not hashed
+    pub hash_max_file_mb: u64,
+}
+
+/// Agent-managed log file. Read before tracing starts, separately from `AgentConfig`.
+pub struct LogFileConfig {
+    /// Log to this file instead of stdout (rotated + zstd-compressed); unset = stdout only
+    pub path: Option<String>,
+    pub max_mb: u64,
+    pub keep: usize,
+    /// Hash-chain log lines and ship signed segments to Core (needs AGENT_API_TOKEN_PATH)
+    pub sealing: bool,
+    /// A segment is closed once its lines reach this size
+    pub seal_segment_kb: usize,
+    /// Closed segments are sealed and shipped this often
+    pub seal_interval_secs: u64,
+    /// Sealed segments waiting for Core
+    pub seal_outbox_dir: String,
+    /// Unshipped segments kept; the oldest is deleted beyond this
+    pub seal_outbox_max: usize,
+}
+
+impl LogFileConfig {
+    pub fn from_env() -> Result<Self, String> {
+        let path = env::var("AGENT_LOG_FILE").ok().filter(|p| !p.is_empty());
+        
+        let max_mb = env::var("AGENT_LOG_MAX_MB")
+            .unwrap_or_else(|_| "16".to_string())
+            .parse::<u64>()
+            .map_err(|_| "AGENT_LOG_MAX_MB must be a valid integer")?;
+        
+        let keep = env::var("AGENT_LOG_KEEP")
+            .unwrap_or_else(|_| "5".to_string())
+            .parse::<usize>()
+            .map_err(|_| "AGENT_LOG_KEEP must be a valid integer")?;
+        
+        if max_mb == 0 {
+            return Err("AGENT_LOG_MAX_MB must be greater than 0".to_string());
+        }
+        
+        let sealing = env::var("AGENT_LOG_SEALING")
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+        
+        let seal_segment_kb = env::var("AGENT_LOG_SEAL_SEGMENT_KB")
+            .unwrap_or_else(|_| "256".to_string())
+            .parse::<usize>()
+            .map_err(|_| "AGENT_LOG_SEAL_SEGMENT_KB must be a valid integer")?;
+        
+        let seal_interval_secs = env::var("AGENT_LOG_SEAL_INTERVAL_SECS")
+            .unwrap_or_else(|_| "300".to_string())
+            .parse::<u64>()
+            .map_err(|_| "AGENT_LOG_SEAL_INTERVAL_SECS must be a valid integer")?;
+        
+        let seal_outbox_dir = env::var("AGENT_LOG_SEAL_OUTBOX_DIR")
+            .unwrap_or_else(|_| "/var/lib/ransomeye/linux_agent/log-segments".to_string());
+        
+        let seal_outbox_max = env::var("AGENT_LOG_SEAL_OUTBOX_MAX")
+            .unwrap_or_else(|_| "1000".to_string())
+            .parse::<usize>()
+            .map_err(|_| "AGENT_LOG_SEAL_OUTBOX_MAX must be a valid integer")?;
+        
+        if sealing && (seal_segment_kb == 0 || seal_interval_secs == 0 || seal_outbox_max == 0) {
+            return Err("AGENT_LOG_SEAL_SEGMENT_KB, AGENT_LOG_SEAL_INTERVAL_SECS and AGENT_LOG_SEAL_OUTBOX_MAX must be greater than 0".to_string());
+        }
+        
+        Ok(LogFileConfig {
+            path,
+            max_mb,
+            keep,
+            sealing,
+            seal_segment_kb,
+            seal_interval_secs,
+            seal_outbox_dir,
+            seal_outbox_max,
+        })
+    }
+    
+    /// Checks against the agent configuration, once it is loaded
+    pub fn validate(&self, config: &AgentConfig) -> Result<(), String> {
+        if self.sealing && config.api_token_path.is_none() {
+            return Err("AGENT_LOG_SEALING needs AGENT_API_TOKEN_PATH".to_string());
+        }
+        Ok(())
+    }
 }
 
 impl AgentConfig {
@@ -40,6 +199,15 @@ impl AgentConfig {
             .parse::<usize>()
             .map_err(|_| "AGENT_MAX_QUEUE_SIZE must be a valid integer")?;
         
+        // Default: one core left to the monitors, at most 4 threads
+        let signing_workers = match env::var("AGENT_SIGNING_WORKERS").ok().filter(|v| !v.is_empty()) {
+            Some(v) => v.parse::<usize>().map_err(|_| "AGENT_SIGNING_WORKERS must be a valid integer")?,
+            None => std::thread::available_parallelism()
+                .map(|n| n.get().saturating_sub(1))
+                .unwrap_or(1)
+                .clamp(1, 4),
+        };
+        
         let rate_limit_tokens = env::var("AGENT_RATE_LIMIT_TOKENS")
             .unwrap_or_else(|_| "1000".to_string())
             .parse::<u64>()
@@ -71,10 +239,172 @@ impl AgentConfig {
         let core_api_url = env::var("CORE_API_URL")
             .unwrap_or_else(|_| "http://localhost:8080".to_string());
         
+        let api_token_path = env::var("AGENT_API_TOKEN_PATH").ok();
+        
+        let delivery_max_attempts = env::var("AGENT_DELIVERY_MAX_ATTEMPTS")
+            .unwrap_or_else(|_| "4".to_string())
+            .parse::<u32>()
+            .map_err(|_| "AGENT_DELIVERY_MAX_ATTEMPTS must be a valid integer")?;
+        
+        let delivery_backoff_base_ms = env::var("AGENT_DELIVERY_BACKOFF_BASE_MS")
+            .unwrap_or_else(|_| "250".to_string())
+            .parse::<u64>()
+            .map_err(|_| "AGENT_DELIVERY_BACKOFF_BASE_MS must be a valid integer")?;
+        
+        let delivery_backoff_max_ms = env::var("AGENT_DELIVERY_BACKOFF_MAX_MS")
+            .unwrap_or_else(|_| "30000".to_string())
+            .parse::<u64>()
+            .map_err(|_| "AGENT_DELIVERY_BACKOFF_MAX_MS must be a valid integer")?;
+        
+        let delivery_retry_budget = env::var("AGENT_DELIVERY_RETRY_BUDGET")
+            .unwrap_or_else(|_| "20".to_string())
+            .parse::<u64>()
+            .map_err(|_| "AGENT_DELIVERY_RETRY_BUDGET must be a valid integer")?;
+        
+        let delivery_retry_refill = env::var("AGENT_DELIVERY_RETRY_REFILL")
+            .unwrap_or_else(|_| "1".to_string())
+            .parse::<u64>()
+            .map_err(|_| "AGENT_DELIVERY_RETRY_REFILL must be a valid integer")?;
+        
+        let circuit_failure_threshold = env::var("AGENT_CIRCUIT_FAILURE_THRESHOLD")
+            .unwrap_or_else(|_| "5".to_string())
+            .parse::<u32>()
+            .map_err(|_| "AGENT_CIRCUIT_FAILURE_THRESHOLD must be a valid integer")?;
+        
+        let circuit_open_secs = env::var("AGENT_CIRCUIT_OPEN_SECS")
+            .unwrap_or_else(|_| "30".to_string())
+            .parse::<u64>()
+            .map_err(|_| "AGENT_CIRCUIT_OPEN_SECS must be a valid integer")?;
+        
+        let bandwidth_bytes_per_sec = env::var("AGENT_BANDWIDTH_BYTES_PER_SEC")
+            .unwrap_or_else(|_| "0".to_string())
+            .parse::<u64>()
+            .map_err(|_| "AGENT_BANDWIDTH_BYTES_PER_SEC must be a valid integer")?;
+        
+        let bandwidth_burst_bytes = env::var("AGENT_BANDWIDTH_BURST_BYTES")
+            .unwrap_or_else(|_| "1048576".to_string())
+            .parse::<u64>()
+            .map_err(|_| "AGENT_BANDWIDTH_BURST_BYTES must be a valid integer")?;
+        
+        let dscp = match env::var("AGENT_DSCP").ok().filter(|v| !v.is_empty()) {
+            Some(v) => Some(v.parse::<u8>().map_err(|_| "AGENT_DSCP must be a valid integer")?),
+            None => None,
+        };
+        
+        let backlog_windows = env::var("AGENT_BACKLOG_WINDOWS").unwrap_or_default();
+        
+        let spool_dir = env::var("AGENT_SPOOL_DIR")
+            .unwrap_or_else(|_| "/var/lib/ransomeye/linux_agent/spool".to_string());
+        
+        let spool_max_mb = env::var("AGENT_SPOOL_MAX_MB")
+            .unwrap_or_else(|_| "256".to_string())
+            .parse::<u64>()
+            .map_err(|_| "AGENT_SPOOL_MAX_MB must be a valid integer")?;
+        
+        let spool_segment_kb = env::var("AGENT_SPOOL_SEGMENT_KB")
+            .unwrap_or_else(|_| "1024".to_string())
+            .parse::<u64>()
+            .map_err(|_| "AGENT_SPOOL_SEGMENT_KB must be a valid integer")?;
+        
+        let disk_budget_mb = env::var("AGENT_DISK_BUDGET_MB")
+            .unwrap_or_else(|_| "512".to_string())
+            .parse::<u64>()
+            .map_err(|_| "AGENT_DISK_BUDGET_MB must be a valid integer")?;
+        
+        let host_root = env::var("AGENT_HOST_ROOT")
+            .unwrap_or_else(|_| "/".to_string());
+        
+        let kubelet_url = env::var("AGENT_KUBELET_URL").ok().filter(|u| !u.is_empty());
+        let kubelet_token_path = env::var("AGENT_KUBELET_TOKEN_PATH").ok();
+        let kubelet_ca_path = env::var("AGENT_KUBELET_CA_PATH").ok();
+        
+        let kubelet_refresh_secs = env::var("AGENT_KUBELET_REFRESH_SECS")
+            .unwrap_or_else(|_| "30".to_string())
+            .parse::<u64>()
+            .map_err(|_| "AGENT_KUBELET_REFRESH_SECS must be a valid integer")?;
+        
+        let memory_acquisition = env::var("AGENT_MEMORY_ACQUISITION")
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+        let memory_full_command = env::var("AGENT_MEMORY_FULL_COMMAND").ok().filter(|c| !c.trim().is_empty());
+        let memory_process_command = env::var("AGENT_MEMORY_PROCESS_COMMAND").ok().filter(|c| !c.trim().is_empty());
+        
+        let memory_work_dir = env::var("AGENT_MEMORY_WORK_DIR")
+            .unwrap_or_else(|_| "/var/lib/ransomeye/linux_agent/memory".to_string());
+        
+        let memory_poll_secs = env::var("AGENT_MEMORY_POLL_SECS")
+            .unwrap_or_else(|_| "30".to_string())
+            .parse::<u64>()
+            .map_err(|_| "AGENT_MEMORY_POLL_SECS must be a valid integer")?;
+        
+        let memory_tool_timeout_secs = env::var("AGENT_MEMORY_TOOL_TIMEOUT_SECS")
+            .unwrap_or_else(|_| "3600".to_string())
+            .parse::<u64>()
+            .map_err(|_| "AGENT_MEMORY_TOOL_TIMEOUT_SECS must be a valid integer")?;
+        
+        let yara_scanning = env::var("AGENT_YARA_SCANNING")
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+        let yara_command = env::var("AGENT_YARA_COMMAND")
+            .unwrap_or_else(|_| "/usr/bin/yara".to_string());
+        let yara_signers = env::var("AGENT_YARA_SIGNERS").unwrap_or_default();
+        let yara_scan_paths = env::var("AGENT_YARA_SCAN_PATHS")
+            .unwrap_or_else(|_| "/home,/tmp,/var/tmp,/dev/shm,/opt,/srv".to_string())
+            .split(',')
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .map(str::to_string)
+            .collect();
+        
+        let yara_scan_interval_secs = env::var("AGENT_YARA_SCAN_INTERVAL_SECS")
+            .unwrap_or_else(|_| "86400".to_string())
+            .parse::<u64>()
+            .map_err(|_| "AGENT_YARA_SCAN_INTERVAL_SECS must be a valid integer")?;
+        
+        let yara_cpu_percent = env::var("AGENT_YARA_CPU_PERCENT")
+            .unwrap_or_else(|_| "20".to_string())
+            .parse::<u32>()
+            .map_err(|_| "AGENT_YARA_CPU_PERCENT must be a valid integer")?;
+        
+        let yara_max_file_mb = env::var("AGENT_YARA_MAX_FILE_MB")
+            .unwrap_or_else(|_| "64".to_string())
+            .parse::<u64>()
+            .map_err(|_| "AGENT_YARA_MAX_FILE_MB must be a valid integer")?;
+        
+        let yara_work_dir = env::var("AGENT_YARA_WORK_DIR")
+            .unwrap_or_else(|_| "/var/lib/ransomeye/linux_agent/yara".to_string());
+        
+        let yara_poll_secs = env::var("AGENT_YARA_POLL_SECS")
+            .unwrap_or_else(|_| "300".to_string())
+            .parse::<u64>()
+            .map_err(|_| "AGENT_YARA_POLL_SECS must be a valid integer")?;
+        
+        let yara_file_timeout_secs = env::var("AGENT_YARA_FILE_TIMEOUT_SECS")
+            .unwrap_or_else(|_| "60".to_string())
+            .parse::<u64>()
+            .map_err(|_| "AGENT_YARA_FILE_TIMEOUT_SECS must be a valid integer")?;
+        
+        let hash_policy_path = env::var("AGENT_HASH_POLICY_PATH").ok().filter(|p| !p.is_empty());
+        let hash_policy_signers = env::var("AGENT_HASH_POLICY_SIGNERS").unwrap_or_default();
+        let hash_policy_enforce = env::var("AGENT_HASH_POLICY_ENFORCE")
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+        
+        let hash_policy_reload_secs = env::var("AGENT_HASH_POLICY_RELOAD_SECS")
+            .unwrap_or_else(|_| "60".to_string())
+            .parse::<u64>()
+            .map_err(|_| "AGENT_HASH_POLICY_RELOAD_SECS must be a valid integer")?;
+        
+        let hash_max_file_mb = env::var("AGENT_HASH_MAX_FILE_MB")
+            .unwrap_or_else(|_| "256".to_string())
+            .parse::<u64>()
+            .map_err(|_| "AGENT_HASH_MAX_FILE_MB must be a valid integer")?;
+        
         Ok(AgentConfig {
             max_processes,
             max_connections,
             max_queue_size,
+            signing_workers,
             rate_limit_tokens,
             rate_limit_refill,
             mass_write_threshold,
@@ -83,6 +413,48 @@ impl AgentConfig {
             enable_ebpf,
             enable_auditd,
             core_api_url,
+            api_token_path,
+            delivery_max_attempts,
+            delivery_backoff_base_ms,
+            delivery_backoff_max_ms,
+            delivery_retry_budget,
+            delivery_retry_refill,
+            circuit_failure_threshold,
+            circuit_open_secs,
+            bandwidth_bytes_per_sec,
+            bandwidth_burst_bytes,
+            dscp,
+            backlog_windows,
+            spool_dir,
+            spool_max_mb,
+            spool_segment_kb,
+            disk_budget_mb,
+            host_root,
+            kubelet_url,
+            kubelet_token_path,
+            kubelet_ca_path,
+            kubelet_refresh_secs,
+            memory_acquisition,
+            memory_full_command,
+            memory_process_command,
+            memory_work_dir,
+            memory_poll_secs,
+            memory_tool_timeout_secs,
+            yara_scanning,
+            yara_command,
+            yara_signers,
+            yara_scan_paths,
+            yara_scan_interval_secs,
+            yara_cpu_percent,
+            yara_max_file_mb,
+            yara_work_dir,
+            yara_poll_secs,
+            yara_file_timeout_secs,
+            hash_policy_path,
+            hash_policy_signers,
+            hash_policy_enforce,
+            hash_policy_reload_secs,
+            hash_max_file_mb,
         })
     }
     
@@ -100,10 +472,101 @@ impl AgentConfig {
             return Err("AGENT_MAX_QUEUE_SIZE must be greater than 0".to_string());
         }
         
+        if self.signing_workers == 0 || self.signing_workers > 16 {
+            return Err("AGENT_SIGNING_WORKERS must be between 1 and 16".to_string());
+        }
+        
         if !self.enable_ebpf && !self.enable_auditd {
             return Err("At least one of ENABLE_EBPF or ENABLE_AUDITD must be true".to_string());
         }
         
+        if self.delivery_max_attempts == 0 {
+            return Err("AGENT_DELIVERY_MAX_ATTEMPTS must be greater than 0".to_string());
+        }
+        
+        if self.delivery_backoff_base_ms == 0 || self.delivery_backoff_max_ms < self.delivery_backoff_base_ms {
+            return Err("AGENT_DELIVERY_BACKOFF_BASE_MS must be > 0 and <= AGENT_DELIVERY_BACKOFF_MAX_MS".to_string());
+        }
+        
+        if self.circuit_failure_threshold == 0 || self.circuit_open_secs == 0 {
+            return Err("AGENT_CIRCUIT_FAILURE_THRESHOLD and AGENT_CIRCUIT_OPEN_SECS must be greater than 0".to_string());
+        }
+        
+        if self.bandwidth_bytes_per_sec > 0 && self.bandwidth_burst_bytes == 0 {
+            return Err("AGENT_BANDWIDTH_BURST_BYTES must be greater than 0 when AGENT_BANDWIDTH_BYTES_PER_SEC is set".to_string());
+        }
+        
+        if self.dscp.is_some_and(|d| d > 63) {
+            return Err("AGENT_DSCP must be between 0 and 63".to_string());
+        }
+        
+        if self.spool_max_mb == 0 {
+            return Err("AGENT_SPOOL_MAX_MB must be greater than 0".to_string());
+        }
+        
+        if self.spool_segment_kb == 0 || self.spool_segment_kb * 1024 > self.spool_max_mb * 1024 * 1024 {
+            return Err("AGENT_SPOOL_SEGMENT_KB must be > 0 and no larger than AGENT_SPOOL_MAX_MB".to_string());
+        }
+        
+        if self.disk_budget_mb == 0 {
+            return Err("AGENT_DISK_BUDGET_MB must be greater than 0".to_string());
+        }
+        
+        if self.kubelet_refresh_secs == 0 {
+            return Err("AGENT_KUBELET_REFRESH_SECS must be greater than 0".to_string());
+        }
+        
+        if self.memory_acquisition {
+            if self.memory_full_command.is_none() && self.memory_process_command.is_none() {
+                return Err("AGENT_MEMORY_ACQUISITION needs AGENT_MEMORY_FULL_COMMAND or AGENT_MEMORY_PROCESS_COMMAND".to_string());
+            }
+            if self.api_token_path.is_none() {
+                return Err("AGENT_MEMORY_ACQUISITION needs AGENT_API_TOKEN_PATH".to_string());
+            }
+            if self.memory_poll_secs == 0 || self.memory_tool_timeout_secs == 0 {
+                return Err("AGENT_MEMORY_POLL_SECS and AGENT_MEMORY_TOOL_TIMEOUT_SECS must be greater than 0".to_string());
+            }
+        }
+        
+        if self.yara_scanning {
+            if self.api_token_path.is_none() {
+                return Err("AGENT_YARA_SCANNING needs AGENT_API_TOKEN_PATH".to_string());
+            }
+            if self.yara_signers.trim().is_empty() {
+                return Err("AGENT_YARA_SCANNING needs AGENT_YARA_SIGNERS".to_string());
+            }
+            if !self.yara_command.starts_with('/') {
+                return Err("AGENT_YARA_COMMAND must be an absolute path".to_string());
+            }
+            if self.yara_scan_paths.is_empty() || self.yara_scan_paths.iter().any(|p| !p.starts_with('/')) {
+                return Err("AGENT_YARA_SCAN_PATHS must list absolute paths".to_string());
+            }
+            if !(1..=100).contains(&self.yara_cpu_percent) {
+                return Err("AGENT_YARA_CPU_PERCENT must be between 1 and 100".to_string());
+            }
+            if self.yara_max_file_mb == 0 || self.yara_poll_secs == 0 || self.yara_file_timeout_secs == 0 {
+                return Err("AGENT_YARA_MAX_FILE_MB, AGENT_YARA_POLL_SECS and AGENT_YARA_FILE_TIMEOUT_SECS must be greater than 0".to_string());
+            }
+        }
+        
+        match &self.hash_policy_path {
+            Some(path) => {
+                if !path.starts_with('/') {
+                    return Err("AGENT_HASH_POLICY_PATH must be an absolute path".to_string());
+                }
+                if self.hash_policy_signers.trim().is_empty() {
+                    return Err("AGENT_HASH_POLICY_PATH needs AGENT_HASH_POLICY_SIGNERS".to_string());
+                }
+                if self.hash_policy_reload_secs == 0 || self.hash_max_file_mb == 0 {
+                    return Err("AGENT_HASH_POLICY_RELOAD_SECS and AGENT_HASH_MAX_FILE_MB must be greater than 0".to_string());
+                }
+            }
+            None if self.hash_policy_enforce => {
+                return Err("AGENT_HASH_POLICY_ENFORCE needs AGENT_HASH_POLICY_PATH".to_string());
+            }
+            None => {}
+        }
+        
         Ok(())
     }
 }
@@ -125,4 +588,109 @@ mod tests {
         config.max_processes = 0;
         assert!(config.validate().is_err());
     }
+    
+    #[test]
+    fn test_delivery_config_validation() {
+        let mut config = AgentConfig::from_env().unwrap();
+        assert!(config.validate().is_ok());
+        config.delivery_backoff_max_ms = config.delivery_backoff_base_ms - 1;
+        assert!(config.validate().is_err());
+    }
+    
+    #[test]
+    fn test_signing_workers_bounds() {
+        let mut config = AgentConfig::from_env().unwrap();
+        assert!((1..=4).contains(&config.signing_workers));
+        config.signing_workers = 0;
+        assert!(config.validate().is_err());
+        config.signing_workers = 17;
+        assert!(config.validate().is_err());
+        config.signing_workers = 16;
+        assert!(config.validate().is_ok());
+    }
+    
+    #[test]
+    fn test_traffic_config_validation() {
+        let mut config = AgentConfig::from_env().unwrap();
+        assert_eq!(config.bandwidth_bytes_per_sec, 0);
+        assert!(config.dscp.is_none());
+        config.bandwidth_bytes_per_sec = 65536;
+        config.bandwidth_burst_bytes = 0;
+        assert!(config.validate().is_err());
+        config.bandwidth_burst_bytes = 262144;
+        assert!(config.validate().is_ok());
+        config.dscp = Some(64);
+        assert!(config.validate().is_err());
+        config.dscp = Some(8);
+        assert!(config.validate().is_ok());
+    }
+    
+    #[test]
+    fn test_memory_acquisition_needs_command_and_token() {
+        let mut config = AgentConfig::from_env().unwrap();
+        assert!(!config.memory_acquisition);
+        config.memory_acquisition = true;
+        config.memory_full_command = None;
+        config.memory_process_command = None;
+        assert!(config.validate().is_err());
+        config.memory_full_command = Some("/usr/bin/avml {output}".to_string());
+        config.api_token_path = None;
+        assert!(config.validate().is_err());
+        config.api_token_path = Some("/etc/ransomeye/agent.token".to_string());
+        assert!(config.validate().is_ok());
+    }
+    
+    #[test]
+    fn test_yara_scanning_needs_signers_and_bounds() {
+        let mut config = AgentConfig::from_env().unwrap();
+        assert!(!config.yara_scanning);
+        config.yara_scanning = true;
+        config.api_token_path = Some("/etc/ransomeye/agent.token".to_string());
+        config.yara_signers = String::new();
+        assert!(config.validate().is_err());
+        config.yara_signers = "ab".repeat(32);
+        assert!(config.validate().is_ok());
+        config.yara_cpu_percent = 0;
+        assert!(config.validate().is_err());
+        config.yara_cpu_percent = 20;
+        config.yara_scan_paths = vec!["home".to_string()];
+        assert!(config.validate().is_err());
+    }
+    
+    #[test]
+    fn test_hash_policy_needs_signers() {
+        let mut config = AgentConfig::from_env().unwrap();
+        assert!(config.hash_policy_path.is_none());
+        config.hash_policy_enforce = true;
+        assert!(config.validate().is_err());
+        config.hash_policy_path = Some("/etc/ransomeye/hash_policy.json".to_string());
+        assert!(config.validate().is_err());
+        config.hash_policy_signers = "ab".repeat(32);
+        assert!(config.validate().is_ok());
+        config.hash_policy_path = Some("hash_policy.json".to_string());
+        assert!(config.validate().is_err());
+    }
+    
+    #[test]
+    fn test_spool_segment_must_fit_spool() {
+        let mut config = AgentConfig::from_env().unwrap();
+        config.spool_max_mb = 1;
+        config.spool_segment_kb = 2048;
+        assert!(config.validate().is_err());
+        config.spool_segment_kb = 512;
+        assert!(config.validate().is_ok());
+    }
+    
+    #[test]
+    fn test_log_sealing_needs_token() {
+        let mut log_config = LogFileConfig::from_env().unwrap();
+        assert!(!log_config.sealing);
+        let mut config = AgentConfig::from_env().unwrap();
+        config.api_token_path = None;
+        assert!(log_config.validate(&config).is_ok());
+        log_config.sealing = true;
+        assert!(log_config.validate(&config).is_err());
+        config.api_token_path = Some("/etc/ransomeye/agent.token".to_string());
+        assert!(log_config.validate(&config).is_ok());
+    }
 }
@@ -3,6 +3,7 @@
 // Details of functionality of this file: Process monitoring tests
 
 use ransomeye_linux_agent::process::{ProcessMonitor, ProcessEventType};
+use ransomeye_linux_agent::lineage::LineageChain;
 
 #[test]
 fn test_process_exec_event() {
@@ -96,3 +97,21 @@ fn test_process_eviction() {
     assert!(count <= 80, "Process count {} should be <= 80 after eviction", count);
 }
 
+
+#[test]
+fn test_process_events_form_lineage_chain() {
+    let chain = LineageChain::new("boot-a".to_string(), "chain-1".to_string());
+    let monitor = ProcessMonitor::with_lineage(1000, chain.clone());
+    
+    let parent = monitor.record_exec(1000, Some(1), 0, 0, "/usr/sbin/sshd".to_string(), None).unwrap();
+    let fork = monitor.record_fork(1000, 1234, 1000, 1000).unwrap();
+    let exec = monitor.record_exec(1234, Some(1000), 1000, 1000, "/usr/bin/test".to_string(), None).unwrap();
+    let mmap = monitor.record_mmap(1234, 0x400000, 4096).unwrap();
+    
+    // Unknown ancestry links to the genesis; each later event to its predecessor in the lineage
+    assert_eq!(parent.lineage.parent_hash, chain.genesis());
+    assert_eq!(fork.lineage.parent_hash, parent.lineage.hash);
+    assert_eq!(exec.lineage.parent_hash, fork.lineage.hash);
+    assert_eq!(mmap.lineage.parent_hash, exec.lineage.hash);
+    assert_eq!(mmap.lineage.boot_id, "boot-a");
+}
@@ -65,3 +65,18 @@ fn test_sequence_numbers_increment() {
     assert!(seq2 > seq1);
 }
 
+#[test]
+fn test_reserved_sequences_sign_out_of_order() {
+    let signer = EventSigner::new().unwrap();
+    
+    let first = signer.reserve_sequence();
+    let second = signer.reserve_sequence();
+    assert_eq!(second, first + 1);
+    
+    // Signed in reverse order, each signature still carries its reserved sequence
+    let signature2 = signer.sign_at(second, b"second");
+    let signature1 = signer.sign_at(first, b"first");
+    assert!(signer.verify(b"first", &signature1, first).unwrap());
+    assert!(signer.verify(b"second", &signature2, second).unwrap());
+    assert!(!signer.verify(b"first", &signature1, second).unwrap());
+}
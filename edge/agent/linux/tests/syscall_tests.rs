@@ -2,7 +2,10 @@
 // Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
 // Details of functionality of this file: Syscall monitoring tests
 
+use std::fs;
+use tempfile::TempDir;
 use ransomeye_linux_agent::syscalls::SyscallMonitor;
+use ransomeye_linux_agent::kernel_caps::{Hook, HookSource, KernelProber};
 
 #[test]
 fn test_syscall_monitor_initialization() {
@@ -17,15 +20,30 @@ fn test_syscall_monitor_initialization() {
 fn test_syscall_monitor_start() {
     let monitor = SyscallMonitor::new();
     
-    // Start should attempt eBPF first, then auditd fallback
-    let result = monitor.start();
+    // Start requires an initialized source (eBPF and/or auditd)
+    assert!(monitor.start().is_err());
+    assert!(!monitor.is_monitoring());
+}
+
+#[test]
+fn test_syscall_monitor_degrades_to_auditd_without_btf() {
+    // Kernel without BTF: CO-RE programs cannot load, auditd serves the audited hooks
+    let root = TempDir::new().unwrap();
+    fs::create_dir_all(root.path().join("proc/sys/kernel")).unwrap();
+    fs::write(root.path().join("proc/sys/kernel/osrelease"), "4.19.0\n").unwrap();
+    let monitor = SyscallMonitor::with_prober(KernelProber::with_root(root.path()));
     
-    // Should succeed (eBPF or auditd initialization)
-    assert!(result.is_ok() || result.is_err()); // Either works or fails gracefully
+    assert!(monitor.init_ebpf().is_err());
+    assert!(!monitor.is_ebpf_enabled());
+    monitor.init_auditd().unwrap();
+    monitor.start().unwrap();
+    assert!(monitor.is_monitoring());
     
-    if result.is_ok() {
-        assert!(monitor.is_monitoring());
-    }
+    let caps = monitor.capabilities().unwrap();
+    assert!(!caps.btf);
+    assert_eq!(caps.count(HookSource::Ebpf), 0);
+    assert_eq!(caps.hook(Hook::ProcessExec).unwrap().source, HookSource::Auditd);
+    assert_eq!(caps.hook(Hook::FileWrite).unwrap().source, HookSource::Unavailable);
 }
 
 #[test]
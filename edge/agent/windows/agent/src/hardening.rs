@@ -17,7 +17,7 @@ use std::time::{Duration, SystemTime, UNIX_EPOCH};
 #[cfg(windows)]
 use std::thread;
 #[cfg(windows)]
-use sha2::{Sha256, Digest};
+use crypto::digest::Sha256;
 #[cfg(windows)]
 use hex;
 #[cfg(windows)]
@@ -4,8 +4,8 @@
 
 #![cfg(feature = "future-windows-agent")]
 
-use ed25519_dalek::{VerifyingKey, Signature, Verifier};
-use sha2::{Sha256, Digest};
+use crypto::digest::Sha256;
+use crypto::signature::verify_ed25519;
 use base64::{Engine as _, engine::general_purpose};
 use hex;
 use thiserror::Error;
@@ -72,18 +72,8 @@ pub fn verify_signature(
         decoded
     };
     
-    // Create VerifyingKey from 32-byte public key
-    let verifying_key = VerifyingKey::from_bytes(&key_bytes.try_into()
-        .map_err(|_| SignatureError::InvalidFormat("Failed to convert public key to array".to_string()))?)
-        .map_err(|e| SignatureError::InvalidFormat(format!("Invalid Ed25519 public key: {:?}", e)))?;
-    
-    // Create Signature from 64-byte signature
-    let sig = Signature::from_bytes(&signature_bytes.try_into()
-        .map_err(|_| SignatureError::InvalidFormat("Failed to convert signature to array".to_string()))?)
-        .map_err(|e| SignatureError::InvalidFormat(format!("Invalid Ed25519 signature: {:?}", e)))?;
-    
-    // Verify signature using Ed25519
-    match verifying_key.verify(data, &sig) {
+    // Verify signature using Ed25519 (selected crypto backend)
+    match verify_ed25519(&key_bytes, data, &signature_bytes) {
         Ok(_) => {
             Ok(true)
         }
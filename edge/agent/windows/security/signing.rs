@@ -4,8 +4,7 @@
 
 #![cfg(feature = "future-windows-agent")]
 
-use ed25519_dalek::{SigningKey, VerifyingKey, Signature, Signer, Verifier};
-use rand::rngs::OsRng;
+use crypto::signature::{verify_ed25519, Ed25519KeyPair, ED25519_PUBLIC_KEY_LEN};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use tracing::{error, debug, info};
@@ -14,25 +13,22 @@ use tracing::{error, debug, info};
 mod errors;
 use errors::AgentError;
 
-/// Event signer using Ed25519
+/// Event signer using Ed25519 through the build's crypto backend
 pub struct EventSigner {
-    signing_key: SigningKey,
-    verifying_key: VerifyingKey,
+    key_pair: Ed25519KeyPair,
     sequence: Arc<AtomicU64>,
 }
 
 impl EventSigner {
     /// Create new event signer
     pub fn new() -> Result<Self, AgentError> {
-        let mut csprng = OsRng;
-        let signing_key = SigningKey::generate(&mut csprng);
-        let verifying_key = signing_key.verifying_key();
+        let key_pair = Ed25519KeyPair::generate()
+            .map_err(|e| AgentError::SigningFailed(format!("Failed to generate Ed25519 key: {}", e)))?;
         
         info!("Event signer created with Ed25519 key");
         
         Ok(Self {
-            signing_key,
-            verifying_key,
+            key_pair,
             sequence: Arc::new(AtomicU64::new(0)),
         })
     }
@@ -50,14 +46,13 @@ impl EventSigner {
             ));
         }
         
-        let signing_key = SigningKey::from_bytes(&key_bytes.try_into().unwrap());
-        let verifying_key = signing_key.verifying_key();
+        let key_pair = Ed25519KeyPair::from_seed(&key_bytes.try_into().unwrap())
+            .map_err(|e| AgentError::SigningFailed(format!("Invalid Ed25519 seed: {}", e)))?;
         
         info!("Event signer loaded from key file");
         
         Ok(Self {
-            signing_key,
-            verifying_key,
+            key_pair,
             sequence: Arc::new(AtomicU64::new(0)),
         })
     }
@@ -72,8 +67,8 @@ impl EventSigner {
         message.extend_from_slice(&seq.to_be_bytes());
         message.extend_from_slice(data);
         
-        let signature: Signature = self.signing_key.sign(&message);
-        let signature_b64 = base64::engine::general_purpose::STANDARD.encode(signature.to_bytes());
+        let signature = self.key_pair.sign(&message);
+        let signature_b64 = base64::engine::general_purpose::STANDARD.encode(signature);
         
         debug!("Event signed: sequence={}, signature_len={}", seq, signature_b64.len());
         Ok(signature_b64)
@@ -92,13 +87,11 @@ impl EventSigner {
             ));
         }
         
-        let signature = Signature::from_bytes(&signature_bytes.try_into().unwrap());
-        
         let mut message = Vec::with_capacity(8 + data.len());
         message.extend_from_slice(&sequence.to_be_bytes());
         message.extend_from_slice(data);
         
-        match self.verifying_key.verify(&message, &signature) {
+        match verify_ed25519(&self.key_pair.public_key(), &message, &signature_bytes) {
             Ok(_) => {
                 debug!("Signature verified: sequence={}", sequence);
                 Ok(true)
@@ -110,9 +103,9 @@ impl EventSigner {
         }
     }
     
-    /// Get verifying key (public key)
-    pub fn verifying_key(&self) -> VerifyingKey {
-        self.verifying_key
+    /// Get verifying key (raw Ed25519 public key)
+    pub fn verifying_key(&self) -> [u8; ED25519_PUBLIC_KEY_LEN] {
+        self.key_pair.public_key()
     }
     
     /// Get current sequence number
@@ -16,6 +16,10 @@ pub struct ProbeConfig {
     pub rate_limit_refill: u64,
     pub identity_path: Option<String>,
     pub signing_key_path: Option<String>,
+    /// Ingest bearer token issued at enrollment; enables triggered captures
+    pub api_token_path: Option<String>,
+    pub capture_dir: String,
+    pub capture_poll_secs: u64,
 }
 
 impl ProbeConfig {
@@ -46,6 +50,15 @@ impl ProbeConfig {
         
         let identity_path = env::var("DPI_IDENTITY_PATH").ok();
         let signing_key_path = env::var("DPI_SIGNING_KEY_PATH").ok();
+        let api_token_path = env::var("DPI_API_TOKEN_PATH").ok();
+        
+        let capture_dir = env::var("DPI_CAPTURE_DIR")
+            .unwrap_or_else(|_| "/var/lib/ransomeye/dpi_probe/captures".to_string());
+        
+        let capture_poll_secs = env::var("DPI_CAPTURE_POLL_SECS")
+            .unwrap_or_else(|_| "30".to_string())
+            .parse::<u64>()
+            .map_err(|_| "DPI_CAPTURE_POLL_SECS must be a valid integer")?;
         
         Ok(ProbeConfig {
             capture_interface,
@@ -55,6 +68,9 @@ impl ProbeConfig {
             rate_limit_refill,
             identity_path,
             signing_key_path,
+            api_token_path,
+            capture_dir,
+            capture_poll_secs,
         })
     }
     
@@ -72,6 +88,14 @@ impl ProbeConfig {
             return Err("DPI_MAX_QUEUE_SIZE must be greater than 0".to_string());
         }
         
+        if !(5..=3600).contains(&self.capture_poll_secs) {
+            return Err("DPI_CAPTURE_POLL_SECS must be between 5 and 3600".to_string());
+        }
+        
+        if !self.capture_dir.starts_with('/') {
+            return Err("DPI_CAPTURE_DIR must be an absolute path".to_string());
+        }
+        
         Ok(())
     }
 }
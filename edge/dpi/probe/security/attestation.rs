@@ -3,11 +3,10 @@
 // Details of functionality of this file: Component attestation for trust verification
 
 use serde::{Serialize, Deserialize};
-use sha2::{Sha256, Digest};
+use crypto::digest::Sha256;
 use hex;
 use tracing::info;
 use base64::Engine;
-use ed25519_dalek::VerifyingKey;
 
 use crate::errors::ProbeError;
 use super::identity::ComponentIdentity;
@@ -27,10 +26,10 @@ pub struct AttestationManager;
 
 impl AttestationManager {
     /// Create attestation for component
-    pub fn create(identity: &ComponentIdentity, verifying_key: &VerifyingKey) -> Result<Attestation, ProbeError> {
+    pub fn create(identity: &ComponentIdentity, verifying_key: &[u8; 32]) -> Result<Attestation, ProbeError> {
         use std::time::{SystemTime, UNIX_EPOCH};
         
-        let verifying_key_b64 = base64::engine::general_purpose::STANDARD.encode(verifying_key.to_bytes());
+        let verifying_key_b64 = base64::engine::general_purpose::STANDARD.encode(verifying_key);
         
         let timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -5,7 +5,7 @@
 use std::fs;
 use std::path::Path;
 use serde::{Serialize, Deserialize};
-use sha2::{Sha256, Digest};
+use crypto::digest::Sha256;
 use hex;
 use tracing::{error, info};
 
@@ -2,9 +2,7 @@
 // Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
 // Details of functionality of this file: Ed25519 event signing with replay-safe sequence numbers
 
-use ed25519_dalek::{SigningKey, VerifyingKey, Signature, Signer, Verifier, SecretKey};
-use rand::rngs::OsRng;
-use rand::RngCore;
+use crypto::signature::{verify_ed25519, Ed25519KeyPair, ED25519_PUBLIC_KEY_LEN};
 use serde::{Serialize, Deserialize};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
@@ -13,27 +11,22 @@ use base64::Engine;
 
 use crate::errors::ProbeError;
 
-/// Event signer using Ed25519
+/// Event signer using Ed25519 through the build's crypto backend
 pub struct EventSigner {
-    signing_key: SigningKey,
-    verifying_key: VerifyingKey,
+    key_pair: Ed25519KeyPair,
     sequence: Arc<AtomicU64>,
 }
 
 impl EventSigner {
     /// Create new event signer
     pub fn new() -> Result<Self, ProbeError> {
-        let mut secret_bytes = [0u8; 32];
-        OsRng.fill_bytes(&mut secret_bytes);
-        let secret_key = SecretKey::from(secret_bytes);
-        let signing_key = SigningKey::from_bytes(&secret_key);
-        let verifying_key = signing_key.verifying_key();
+        let key_pair = Ed25519KeyPair::generate()
+            .map_err(|e| ProbeError::SigningFailed(format!("Failed to generate Ed25519 key: {}", e)))?;
         
         info!("Event signer created with Ed25519 key");
         
         Ok(Self {
-            signing_key,
-            verifying_key,
+            key_pair,
             sequence: Arc::new(AtomicU64::new(0)),
         })
     }
@@ -51,14 +44,13 @@ impl EventSigner {
             ));
         }
         
-        let signing_key = SigningKey::from_bytes(&key_bytes.try_into().unwrap());
-        let verifying_key = signing_key.verifying_key();
+        let key_pair = Ed25519KeyPair::from_seed(&key_bytes.try_into().unwrap())
+            .map_err(|e| ProbeError::SigningFailed(format!("Invalid Ed25519 seed: {}", e)))?;
         
         info!("Event signer loaded from key file");
         
         Ok(Self {
-            signing_key,
-            verifying_key,
+            key_pair,
             sequence: Arc::new(AtomicU64::new(0)),
         })
     }
@@ -67,6 +59,12 @@ impl EventSigner {
     /// 
     /// Includes replay-safe sequence number.
     pub fn sign(&self, data: &[u8]) -> Result<String, ProbeError> {
+        self.sign_with_sequence(data).map(|(_, signature_b64)| signature_b64)
+    }
+    
+    /// Sign event data and return the sequence number it was signed under
+    /// (sent alongside the signature so the receiver can rebuild the message).
+    pub fn sign_with_sequence(&self, data: &[u8]) -> Result<(u64, String), ProbeError> {
         // Get next sequence number (replay-safe)
         let seq = self.sequence.fetch_add(1, Ordering::AcqRel);
         
@@ -76,13 +74,13 @@ impl EventSigner {
         message.extend_from_slice(data);
         
         // Sign message
-        let signature: Signature = self.signing_key.sign(&message);
+        let signature = self.key_pair.sign(&message);
         
         // Encode signature as base64
-        let signature_b64 = base64::engine::general_purpose::STANDARD.encode(signature.to_bytes());
+        let signature_b64 = base64::engine::general_purpose::STANDARD.encode(signature);
         
         debug!("Event signed: sequence={}, signature_len={}", seq, signature_b64.len());
-        Ok(signature_b64)
+        Ok((seq, signature_b64))
     }
     
     /// Verify signature
@@ -99,15 +97,13 @@ impl EventSigner {
             ));
         }
         
-        let signature = Signature::from_bytes(&signature_bytes.try_into().unwrap());
-        
         // Reconstruct message: sequence || data
         let mut message = Vec::with_capacity(8 + data.len());
         message.extend_from_slice(&sequence.to_be_bytes());
         message.extend_from_slice(data);
         
         // Verify signature
-        match self.verifying_key.verify(&message, &signature) {
+        match verify_ed25519(&self.key_pair.public_key(), &message, &signature_bytes) {
             Ok(_) => {
                 debug!("Signature verified: sequence={}", sequence);
                 Ok(true)
@@ -119,9 +115,9 @@ impl EventSigner {
         }
     }
     
-    /// Get verifying key (public key)
-    pub fn verifying_key(&self) -> VerifyingKey {
-        self.verifying_key
+    /// Get verifying key (raw Ed25519 public key)
+    pub fn verifying_key(&self) -> [u8; ED25519_PUBLIC_KEY_LEN] {
+        self.key_pair.public_key()
     }
     
     /// Get current sequence number
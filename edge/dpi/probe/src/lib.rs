@@ -24,6 +24,7 @@ pub mod envelope;
 pub mod backpressure;
 pub mod rate_limit;
 pub mod health;
+pub mod triggered_capture;
 
 // Security module is in probe/security/
 
@@ -36,4 +37,5 @@ pub use envelope::EventEnvelope;
 pub use backpressure::BackpressureManager;
 pub use rate_limit::RateLimiter;
 pub use health::HealthMonitor;
+pub use triggered_capture::TriggeredCaptures;
 
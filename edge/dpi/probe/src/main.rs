@@ -7,7 +7,7 @@ use tracing::{info, error};
 use std::time::{SystemTime, UNIX_EPOCH};
 use reqwest::Client as ReqwestClient;
 use chrono::{DateTime, Utc};
-use sha2::{Sha256, Digest};
+use crypto::digest::Sha256;
 use uuid::Uuid;
 use tokio::runtime::Runtime;
 
@@ -21,6 +21,7 @@ pub mod backpressure;
 pub mod rate_limit;
 pub mod health;
 pub mod hardening;
+pub mod triggered_capture;
 
 #[path = "../security/mod.rs"]
 pub mod security;
@@ -35,6 +36,7 @@ use backpressure::BackpressureManager;
 use rate_limit::RateLimiter;
 use health::HealthMonitor;
 use hardening::RuntimeHardening;
+use triggered_capture::TriggeredCaptures;
 use security::{IdentityManager, EventSigner};
 #[path = "../../config/validation.rs"]
 mod config_validation;
@@ -47,6 +49,11 @@ fn main() -> Result<(), ProbeError> {
     
     info!("RansomEye DPI Probe starting...");
     
+    // Crypto backend self-check (FAIL-CLOSED): known answers, and FIPS mode for FIPS builds
+    crypto::self_check()
+        .map_err(|e| ProbeError::ConfigurationError(format!("Crypto self-check failed: {}", e)))?;
+    info!("Crypto backend: {}", crypto::backend());
+    
     // Get binary path for integrity verification
     let binary_path = std::env::current_exe()
         .map_err(|e| ProbeError::ConfigurationError(format!("Failed to get binary path: {}", e)))?
@@ -117,6 +124,38 @@ fn main() -> Result<(), ProbeError> {
     
     info!("HTTP client initialized for direct delivery to {}", core_api_url);
     
+    // Per-probe ingest bearer token (issued at enrollment). FAIL-CLOSED if configured but unreadable.
+    let api_token: Option<String> = match config.api_token_path.as_ref() {
+        Some(path) => {
+            let token = std::fs::read_to_string(path)
+                .map_err(|e| ProbeError::ConfigurationError(format!("Failed to read DPI_API_TOKEN_PATH {}: {}", path, e)))?
+                .trim()
+                .to_string();
+            if token.is_empty() {
+                return Err(ProbeError::ConfigurationError(format!("Ingest API token file {} is empty", path)));
+            }
+            info!("Ingest API token loaded from {}", path);
+            Some(token)
+        }
+        None => None,
+    };
+    
+    // Triggered packet captures for critical detections (control channel needs the token)
+    match api_token {
+        Some(token) => {
+            TriggeredCaptures::new(
+                core_api_url.clone(),
+                token,
+                config.capture_interface.clone(),
+                std::path::PathBuf::from(&config.capture_dir),
+                std::time::Duration::from_secs(config.capture_poll_secs),
+            )?
+            .spawn()?;
+            info!("Triggered captures enabled (poll every {}s)", config.capture_poll_secs);
+        }
+        None => info!("Triggered captures disabled (no DPI_API_TOKEN_PATH)"),
+    }
+    
     // Create tokio runtime for async HTTP calls
     let rt = Runtime::new()
         .map_err(|e| ProbeError::ConfigurationError(format!("Failed to create runtime: {}", e)))?;
@@ -260,21 +299,27 @@ fn main() -> Result<(), ProbeError> {
                 
                 info!("Signing payload hash={} envelope_id={}", payload_hash, envelope.event_id);
                 
-                // Step 3: Sign the hash (using Ed25519 signer)
-                // Note: The envelope already has a signature, but we need to sign the hash
-                // For now, we'll use the existing signature from the envelope
-                // In production, this should be a proper hash signature
-                let signature_b64 = envelope.signature.clone();
+                // Step 3: Sign the hash (using Ed25519 signer) under the next sequence number;
+                // ingest verifies sequence || hash against the probe's registered key
+                let (sequence, signature_b64) = signer.sign_with_sequence(&hash_bytes)
+                    .map_err(|e| ProbeError::SigningFailed(format!("{}", e)))?;
                 
-                // Step 4: Create SignedEvent with new format
+                // Step 4: Create SignedEvent with the canonical bytes embedded verbatim as "envelope",
+                // so ingest can recompute payload_hash from the envelope it receives
                 use serde_json::json;
-                let signed_event = json!({
-                    "envelope": serde_json::from_slice::<serde_json::Value>(&canonical_bytes)
-                        .map_err(|e| ProbeError::ConfigurationError(format!("Failed to parse envelope JSON: {}", e)))?,
+                let fields = serde_json::to_vec(&json!({
                     "payload_hash": payload_hash,
                     "signature": signature_b64,
                     "signer_id": identity.component_id(),
-                });
+                    "sequence": sequence,
+                }))
+                .map_err(|e| ProbeError::ConfigurationError(format!("Failed to serialize signed event: {}", e)))?;
+                let mut signed_event = Vec::with_capacity(canonical_bytes.len() + fields.len() + 16);
+                signed_event.extend_from_slice(b"{\"envelope\":");
+                signed_event.extend_from_slice(&canonical_bytes);
+                signed_event.push(b',');
+                // fields is a non-empty JSON object: append its members after the envelope
+                signed_event.extend_from_slice(&fields[1..]);
                 
                 // Send directly via HTTP POST (async call in sync context)
                 let url = format!("{}/ingest/dpi", core_api_url);
@@ -286,7 +331,8 @@ fn main() -> Result<(), ProbeError> {
                 match rt.block_on(async move {
                     let res = client_clone
                         .post(&url)
-                        .json(&signed_event)
+                        .header(reqwest::header::CONTENT_TYPE, "application/json")
+                        .body(signed_event)
                         .send()
                         .await?;
                     Ok::<_, reqwest::Error>(res)
@@ -0,0 +1,250 @@
+// Path and File Name : /home/ransomeye/rebuild/ransomeye_dpi_probe/probe/src/triggered_capture.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Triggered packet captures - polls Core for capture requests raised by critical detections, records a bounded, host-filtered pcap and uploads it with its SHA-256
+
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crypto::digest::Sha256;
+use pcap::{Capture, Device};
+use reqwest::Client as ReqwestClient;
+use serde::Deserialize;
+use tokio::runtime::Runtime;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use super::errors::ProbeError;
+
+/// Longest capture Core may request
+pub const MAX_DURATION_SECS: i32 = 3600;
+/// Largest pcap Core may request
+pub const MAX_CAPTURE_BYTES: i64 = 1024 * 1024 * 1024;
+const MIN_SNAPLEN: i32 = 64;
+const MAX_SNAPLEN: i32 = 262_144;
+/// pcap global header and per-record header sizes (file size accounting)
+const PCAP_HEADER_LEN: u64 = 24;
+const PCAP_RECORD_HEADER_LEN: u64 = 16;
+/// Upload timeout (a pcap may be up to MAX_CAPTURE_BYTES)
+const UPLOAD_TIMEOUT_SECS: u64 = 600;
+/// Core refuses longer failure reasons
+const MAX_FAILURE_REASON_CHARS: usize = 512;
+
+/// Capture request handed out by POST /probes/captures/claim
+#[derive(Debug, Clone, Deserialize)]
+pub struct CaptureOrder {
+    pub capture_id: Uuid,
+    pub bpf_filter: String,
+    pub duration_secs: i32,
+    pub max_bytes: i64,
+    pub snaplen: i32,
+}
+
+/// Refuse anything but a bounded capture of one host.
+///
+/// The filter is parsed, not passed through: Core only ever asks for "host <address>", and a
+/// probe must never capture more than that.
+pub fn validate_order(order: &CaptureOrder) -> Result<IpAddr, ProbeError> {
+    let host = order
+        .bpf_filter
+        .strip_prefix("host ")
+        .and_then(|a| a.parse::<IpAddr>().ok())
+        .ok_or_else(|| ProbeError::ConfigurationError(format!("Refusing capture filter '{}'", order.bpf_filter)))?;
+    if !(1..=MAX_DURATION_SECS).contains(&order.duration_secs) {
+        return Err(ProbeError::ConfigurationError(format!("Capture duration {}s out of bounds", order.duration_secs)));
+    }
+    if !(PCAP_HEADER_LEN as i64 + 1..=MAX_CAPTURE_BYTES).contains(&order.max_bytes) {
+        return Err(ProbeError::ConfigurationError(format!("Capture size {} bytes out of bounds", order.max_bytes)));
+    }
+    if !(MIN_SNAPLEN..=MAX_SNAPLEN).contains(&order.snaplen) {
+        return Err(ProbeError::ConfigurationError(format!("Capture snaplen {} out of bounds", order.snaplen)));
+    }
+    Ok(host)
+}
+
+/// Triggered capture worker (own thread, own runtime; never touches the telemetry capture)
+pub struct TriggeredCaptures {
+    client: ReqwestClient,
+    core_api_url: String,
+    api_token: String,
+    interface: String,
+    capture_dir: PathBuf,
+    poll_interval: Duration,
+}
+
+impl TriggeredCaptures {
+    pub fn new(
+        core_api_url: String,
+        api_token: String,
+        interface: String,
+        capture_dir: PathBuf,
+        poll_interval: Duration,
+    ) -> Result<Self, ProbeError> {
+        std::fs::create_dir_all(&capture_dir).map_err(|e| {
+            ProbeError::ConfigurationError(format!("Failed to create capture directory {}: {}", capture_dir.display(), e))
+        })?;
+        let client = ReqwestClient::builder()
+            .timeout(Duration::from_secs(UPLOAD_TIMEOUT_SECS))
+            .build()
+            .map_err(|e| ProbeError::ConfigurationError(format!("Failed to create HTTP client: {}", e)))?;
+        Ok(Self { client, core_api_url, api_token, interface, capture_dir, poll_interval })
+    }
+
+    /// Poll for capture requests until the process exits
+    pub fn spawn(self) -> Result<std::thread::JoinHandle<()>, ProbeError> {
+        let rt = Runtime::new()
+            .map_err(|e| ProbeError::ConfigurationError(format!("Failed to create runtime: {}", e)))?;
+        std::thread::Builder::new()
+            .name("triggered-capture".to_string())
+            .spawn(move || loop {
+                match self.claim(&rt) {
+                    Ok(Some(order)) => self.run_order(&rt, &order),
+                    Ok(None) => std::thread::sleep(self.poll_interval),
+                    Err(e) => {
+                        warn!("Capture request poll failed: {}", e);
+                        std::thread::sleep(self.poll_interval);
+                    }
+                }
+            })
+            .map_err(|e| ProbeError::ConfigurationError(format!("Failed to start capture thread: {}", e)))
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.core_api_url, path)
+    }
+
+    fn claim(&self, rt: &Runtime) -> Result<Option<CaptureOrder>, ProbeError> {
+        rt.block_on(async {
+            let res = self
+                .client
+                .post(self.url("/probes/captures/claim"))
+                .bearer_auth(&self.api_token)
+                .send()
+                .await
+                .map_err(|e| ProbeError::CaptureFailed(format!("claim: {}", e)))?;
+            match res.status() {
+                reqwest::StatusCode::NO_CONTENT => Ok(None),
+                s if s.is_success() => res
+                    .json::<CaptureOrder>()
+                    .await
+                    .map(Some)
+                    .map_err(|e| ProbeError::CaptureFailed(format!("claim: invalid order: {}", e))),
+                s => Err(ProbeError::CaptureFailed(format!("claim: HTTP {}", s))),
+            }
+        })
+    }
+
+    fn run_order(&self, rt: &Runtime, order: &CaptureOrder) {
+        let path = self.capture_dir.join(format!("{}.pcap", order.capture_id));
+        let result = validate_order(order)
+            .and_then(|host| {
+                info!(
+                    "Triggered capture {} of {} for {}s (max {} bytes)",
+                    order.capture_id, host, order.duration_secs, order.max_bytes
+                );
+                self.capture(order, &path)
+            })
+            .and_then(|packets| {
+                info!("Triggered capture {} finished with {} packet(s)", order.capture_id, packets);
+                self.upload(rt, order, &path)
+            });
+        let _ = std::fs::remove_file(&path);
+        if let Err(e) = result {
+            error!("Triggered capture {} failed: {}", order.capture_id, e);
+            self.report_failure(rt, order, &e.to_string());
+        }
+    }
+
+    /// Record filtered packets to `path` until the deadline or the size bound; returns packets written
+    fn capture(&self, order: &CaptureOrder, path: &Path) -> Result<u64, ProbeError> {
+        let device = Device::list()
+            .map_err(|e| ProbeError::CaptureFailed(format!("Failed to list devices: {}", e)))?
+            .into_iter()
+            .find(|d| d.name == self.interface)
+            .ok_or_else(|| ProbeError::CaptureFailed(format!("Interface not found: {}", self.interface)))?;
+        let mut cap = Capture::from_device(device)
+            .map_err(|e| ProbeError::CaptureFailed(format!("Failed to open device: {}", e)))?
+            .promisc(true)
+            .snaplen(order.snaplen)
+            .timeout(1000)
+            .open()
+            .map_err(|e| ProbeError::CaptureFailed(format!("Failed to activate capture: {}", e)))?;
+        cap.filter(&order.bpf_filter, true)
+            .map_err(|e| ProbeError::CaptureFailed(format!("Failed to apply filter '{}': {}", order.bpf_filter, e)))?;
+        let mut file = cap
+            .savefile(path)
+            .map_err(|e| ProbeError::CaptureFailed(format!("Failed to create {}: {}", path.display(), e)))?;
+
+        let deadline = Instant::now() + Duration::from_secs(order.duration_secs as u64);
+        let max_bytes = order.max_bytes as u64;
+        let mut written = PCAP_HEADER_LEN;
+        let mut packets = 0u64;
+        while Instant::now() < deadline {
+            match cap.next_packet() {
+                Ok(packet) => {
+                    let record = PCAP_RECORD_HEADER_LEN + packet.header.caplen as u64;
+                    if written + record > max_bytes {
+                        info!("Triggered capture {} reached its size bound", order.capture_id);
+                        break;
+                    }
+                    file.write(&packet);
+                    written += record;
+                    packets += 1;
+                }
+                Err(pcap::Error::TimeoutExpired) => continue,
+                Err(e) => return Err(ProbeError::CaptureFailed(format!("Capture error: {}", e))),
+            }
+        }
+        file.flush()
+            .map_err(|e| ProbeError::CaptureFailed(format!("Failed to write {}: {}", path.display(), e)))?;
+        Ok(packets)
+    }
+
+    fn upload(&self, rt: &Runtime, order: &CaptureOrder, path: &Path) -> Result<(), ProbeError> {
+        let pcap = std::fs::read(path)
+            .map_err(|e| ProbeError::CaptureFailed(format!("Failed to read {}: {}", path.display(), e)))?;
+        if pcap.len() as u64 > order.max_bytes as u64 {
+            return Err(ProbeError::CaptureFailed(format!("pcap of {} bytes exceeds the capture bound", pcap.len())));
+        }
+        let mut hasher = Sha256::new();
+        hasher.update(&pcap);
+        let sha256 = hex::encode(hasher.finalize());
+        let bytes = pcap.len();
+        rt.block_on(async {
+            let res = self
+                .client
+                .post(self.url("/probes/captures/upload"))
+                .bearer_auth(&self.api_token)
+                .header("X-Capture-Id", order.capture_id.to_string())
+                .header("X-Content-SHA256", &sha256)
+                .header(reqwest::header::CONTENT_TYPE, "application/vnd.tcpdump.pcap")
+                .body(pcap)
+                .send()
+                .await
+                .map_err(|e| ProbeError::CaptureFailed(format!("upload: {}", e)))?;
+            if !res.status().is_success() {
+                return Err(ProbeError::CaptureFailed(format!("upload: HTTP {}", res.status())));
+            }
+            Ok(())
+        })?;
+        info!("Triggered capture {} uploaded | bytes={} | sha256={}", order.capture_id, bytes, sha256);
+        Ok(())
+    }
+
+    fn report_failure(&self, rt: &Runtime, order: &CaptureOrder, reason: &str) {
+        let reason: String = reason.chars().take(MAX_FAILURE_REASON_CHARS).collect();
+        let sent = rt.block_on(async {
+            self.client
+                .post(self.url("/probes/captures/fail"))
+                .bearer_auth(&self.api_token)
+                .json(&serde_json::json!({"capture_id": order.capture_id, "reason": reason}))
+                .send()
+                .await
+        });
+        match sent {
+            Ok(res) if res.status().is_success() => {}
+            Ok(res) => warn!("Failed to report capture {} failure: HTTP {}", order.capture_id, res.status()),
+            Err(e) => warn!("Failed to report capture {} failure: {}", order.capture_id, e),
+        }
+    }
+}
@@ -0,0 +1,274 @@
+// Path and File Name : /home/ransomeye/rebuild/ops/client/src/client.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: RansomEye API client - base URL and credentials (agent token for ingest, admin key or operator bearer for the admin API), retried requests and the ingest, fleet, incident and export calls
+
+/*
+ * RansomEye API Client
+ *
+ * Credentials per API:
+ *   - ingest API (/ingest/linux, /ingest/dpi): Authorization: Bearer <agent token> (issued at enrollment)
+ *   - admin API (/admin/...): X-Admin-Key: <key>, or Authorization: Bearer <operator session or IdP token>
+ *
+ * A call whose credentials are not configured fails before anything is sent. Every call goes
+ * through the retry policy (see retry.rs); a refused request ends as ClientError::Api with the
+ * status and the service's reason ("error" field of a JSON body, else the body text).
+ */
+
+use std::time::Duration;
+
+use reqwest::{Method, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tracing::debug;
+
+use crate::errors::ClientError;
+use crate::export::{ExportRequest, ExportStream};
+use crate::list::{ListParams, Pager};
+use crate::models::{CloseIncident, ExportStats, FleetOverview, HostInventory, IncidentClosure, IngestResponse, SignedEvent};
+use crate::retry::RetryPolicy;
+
+/// Default per-request timeout (export responses have their own, see `ExportRequest::response_timeout`)
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Credentials of the admin API.
+#[derive(Clone)]
+pub enum AdminCredentials {
+    /// X-Admin-Key
+    AdminKey(String),
+    /// Operator session from /auth/oidc/callback or /auth/break-glass, or an IdP access token
+    OperatorToken(String),
+}
+
+impl std::fmt::Debug for AdminCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AdminCredentials::AdminKey(_) => f.write_str("AdminKey(<redacted>)"),
+            AdminCredentials::OperatorToken(_) => f.write_str("OperatorToken(<redacted>)"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Auth {
+    Agent,
+    Admin,
+}
+
+/// Client of one RansomEye core instance. Cheap to clone; clones share the connection pool.
+#[derive(Clone)]
+pub struct RansomEyeClient {
+    http: reqwest::Client,
+    base_url: String,
+    admin: Option<AdminCredentials>,
+    agent_token: Option<String>,
+    retry: RetryPolicy,
+}
+
+impl RansomEyeClient {
+    /// Client for `base_url` (e.g. `https://core.example.com:8443`) with the default timeout and retry policy
+    pub fn new(base_url: &str) -> Result<Self, ClientError> {
+        let http = reqwest::Client::builder()
+            .timeout(DEFAULT_TIMEOUT)
+            .build()
+            .map_err(|e| ClientError::Config(format!("Failed to create HTTP client: {}", e)))?;
+        Self::with_http_client(base_url, http)
+    }
+
+    /// Client on a caller-built reqwest client (custom CA, client certificate, proxy, timeout)
+    pub fn with_http_client(base_url: &str, http: reqwest::Client) -> Result<Self, ClientError> {
+        let url = reqwest::Url::parse(base_url)
+            .map_err(|e| ClientError::Config(format!("Invalid base URL {}: {}", base_url, e)))?;
+        if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+            return Err(ClientError::Config(format!("Base URL {} must be http(s)://host[:port]", base_url)));
+        }
+        Ok(Self {
+            http,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            admin: None,
+            agent_token: None,
+            retry: RetryPolicy::default(),
+        })
+    }
+
+    pub fn with_admin_key(mut self, key: impl Into<String>) -> Self {
+        self.admin = Some(AdminCredentials::AdminKey(key.into()));
+        self
+    }
+
+    pub fn with_operator_token(mut self, token: impl Into<String>) -> Self {
+        self.admin = Some(AdminCredentials::OperatorToken(token.into()));
+        self
+    }
+
+    /// Agent bearer token for the ingest API
+    pub fn with_agent_token(mut self, token: impl Into<String>) -> Self {
+        self.agent_token = Some(token.into());
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub(crate) fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry
+    }
+
+    // ---- Ingest ----
+
+    /// POST /ingest/linux. A 200 with status "quarantined" is an accepted event, not an error.
+    pub async fn ingest_linux(&self, event: &SignedEvent) -> Result<IngestResponse, ClientError> {
+        self.post_json(Auth::Agent, "/ingest/linux", event, true).await
+    }
+
+    /// POST /ingest/dpi
+    pub async fn ingest_dpi(&self, event: &SignedEvent) -> Result<IngestResponse, ClientError> {
+        self.post_json(Auth::Agent, "/ingest/dpi", event, true).await
+    }
+
+    // ---- Fleet ----
+
+    /// GET /admin/fleet/inventory, page by page (ordered by hostname)
+    pub fn fleet_inventory(&self, params: ListParams) -> Pager<'_, HostInventory> {
+        self.list("/admin/fleet/inventory", params)
+    }
+
+    /// GET /admin/dashboard/fleet-summary
+    pub async fn fleet_summary(&self) -> Result<FleetOverview, ClientError> {
+        self.get_json(Auth::Admin, "/admin/dashboard/fleet-summary", &[]).await
+    }
+
+    // ---- Incidents ----
+
+    /// POST /admin/incidents/close. Not repeated after a transport error: the first attempt may
+    /// have closed the incident, and a repeat would answer 409.
+    pub async fn close_incident(&self, close: &CloseIncident) -> Result<IncidentClosure, ClientError> {
+        self.post_json(Auth::Admin, "/admin/incidents/close", close, false).await
+    }
+
+    // ---- Export ----
+
+    /// GET /admin/export/events as one stream of events across responses (see `ExportStream`)
+    pub fn export_events(&self, request: ExportRequest) -> ExportStream<'_> {
+        ExportStream::new(self, request)
+    }
+
+    /// GET /admin/export/stats
+    pub async fn export_stats(&self) -> Result<ExportStats, ClientError> {
+        self.get_json(Auth::Admin, "/admin/export/stats", &[]).await
+    }
+
+    // ---- Generic ----
+
+    /// Any /admin list endpoint (see the list conventions in the OpenAPI contract). Use
+    /// `serde_json::Value` items together with a sparse fieldset.
+    pub fn list<T: DeserializeOwned>(&self, path: &str, params: ListParams) -> Pager<'_, T> {
+        Pager::new(self, path, params)
+    }
+
+    pub(crate) async fn get_json<T: DeserializeOwned>(
+        &self,
+        auth: Auth,
+        path: &str,
+        query: &[(String, String)],
+    ) -> Result<T, ClientError> {
+        let res = self.send(auth, Method::GET, path, true, |req| req.query(query)).await?;
+        res.json().await.map_err(|e| ClientError::Decode(format!("GET {}: {}", path, e)))
+    }
+
+    async fn post_json<B: Serialize, T: DeserializeOwned>(
+        &self,
+        auth: Auth,
+        path: &str,
+        body: &B,
+        idempotent: bool,
+    ) -> Result<T, ClientError> {
+        let res = self.send(auth, Method::POST, path, idempotent, |req| req.json(body)).await?;
+        res.json().await.map_err(|e| ClientError::Decode(format!("POST {}: {}", path, e)))
+    }
+
+    /// Send with credentials and retries; Ok only for a 2xx response
+    pub(crate) async fn send<F>(
+        &self,
+        auth: Auth,
+        method: Method,
+        path: &str,
+        idempotent: bool,
+        build: F,
+    ) -> Result<Response, ClientError>
+    where
+        F: Fn(RequestBuilder) -> RequestBuilder,
+    {
+        let url = format!("{}{}", self.base_url, path);
+        let attempts = self.retry.max_attempts.max(1);
+        for attempt in 1..=attempts {
+            let req = self.authorize(auth, self.http.request(method.clone(), &url))?;
+            let delay = match build(req).send().await {
+                Ok(res) if res.status().is_success() => return Ok(res),
+                Ok(res) => match self.retry.delay_for(res.status(), res.headers(), attempt - 1, idempotent) {
+                    Some(delay) if attempt < attempts => {
+                        debug!("{} {} answered {}, retrying in {:?}", method, path, res.status(), delay);
+                        delay
+                    }
+                    _ => return Err(api_error(res).await),
+                },
+                Err(e) if idempotent && attempt < attempts && !e.is_builder() => {
+                    let delay = self.retry.backoff(attempt - 1);
+                    debug!("{} {} failed ({}), retrying in {:?}", method, path, e, delay);
+                    delay
+                }
+                Err(e) => return Err(e.into()),
+            };
+            tokio::time::sleep(delay).await;
+        }
+        unreachable!("the last attempt always returns")
+    }
+
+    fn authorize(&self, auth: Auth, req: RequestBuilder) -> Result<RequestBuilder, ClientError> {
+        match auth {
+            Auth::Agent => match &self.agent_token {
+                Some(token) => Ok(req.bearer_auth(token)),
+                None => Err(ClientError::MissingCredentials("the ingest API (agent token)")),
+            },
+            Auth::Admin => match &self.admin {
+                Some(AdminCredentials::AdminKey(key)) => Ok(req.header("x-admin-key", key)),
+                Some(AdminCredentials::OperatorToken(token)) => Ok(req.bearer_auth(token)),
+                None => Err(ClientError::MissingCredentials("the admin API (admin key or operator token)")),
+            },
+        }
+    }
+}
+
+impl std::fmt::Debug for RansomEyeClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RansomEyeClient")
+            .field("base_url", &self.base_url)
+            .field("admin", &self.admin)
+            .field("agent_token", &self.agent_token.as_ref().map(|_| "<redacted>"))
+            .field("retry", &self.retry)
+            .finish()
+    }
+}
+
+/// Status and reason of a refused request
+pub(crate) async fn api_error(res: Response) -> ClientError {
+    let status = res.status();
+    let body = res.text().await.unwrap_or_default();
+    let message = serde_json::from_str::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|v| v.get("error").and_then(|e| e.as_str()).map(str::to_string))
+        .unwrap_or_else(|| {
+            let text = body.trim();
+            if text.is_empty() {
+                status.canonical_reason().unwrap_or("request refused").to_string()
+            } else {
+                text.chars().take(512).collect()
+            }
+        });
+    ClientError::Api { status: status.as_u16(), message }
+}
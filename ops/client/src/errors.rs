@@ -0,0 +1,49 @@
+// Path and File Name : /home/ransomeye/rebuild/ops/client/src/errors.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Error types for the RansomEye API client - refused requests keep their HTTP status and the service's reason, missing credentials fail before anything is sent
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("Configuration error: {0}")]
+    Config(String),
+
+    #[error("No credentials for {0}")]
+    MissingCredentials(&'static str),
+
+    /// The service answered with a non-success status (after any retries)
+    #[error("HTTP {status}: {message}")]
+    Api { status: u16, message: String },
+
+    /// Connection, TLS or timeout failure (after any retries)
+    #[error("Transport error: {0}")]
+    Transport(String),
+
+    #[error("Invalid response: {0}")]
+    Decode(String),
+
+    /// An export response kept ending early without progress
+    #[error("Export stream interrupted: {0}")]
+    Interrupted(String),
+}
+
+impl ClientError {
+    /// HTTP status of a refused request
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            ClientError::Api { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ClientError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_decode() {
+            ClientError::Decode(e.to_string())
+        } else {
+            ClientError::Transport(e.to_string())
+        }
+    }
+}
@@ -0,0 +1,228 @@
+// Path and File Name : /home/ransomeye/rebuild/ops/client/src/export.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Data export session over /admin/export/events - NDJSON read incrementally, cursor kept per event, next responses requested and interrupted responses resumed until the session is exhausted
+
+/*
+ * Data Export Stream
+ *
+ * A session starts with the window and filters; every later response of the session is
+ * requested with a cursor only. Each event line carries the cursor just after it, and a
+ * complete response ends with {type: end, next_cursor}: a cursor means more events, null means
+ * the session is exhausted. A response that ends without an end line (read error, connection
+ * reset, timeout) is resumed from the cursor of the last event read, so no event is lost or
+ * repeated. A resume that yields no event counts against the retry policy's attempts.
+ */
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use reqwest::{Method, Response};
+use tracing::{debug, warn};
+
+use crate::client::{Auth, RansomEyeClient};
+use crate::errors::ClientError;
+use crate::models::{ExportLine, ExportedEvent};
+
+/// Event lines per response when not set (the service allows up to 1000000)
+pub const DEFAULT_PAGE_LIMIT: u64 = 10_000;
+
+/// Default time allowed for one export response, body included
+pub const DEFAULT_RESPONSE_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Window and filters of a new export session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportRequest {
+    /// Start of the normalized_at window, inclusive
+    pub since: DateTime<Utc>,
+    /// End of the window, exclusive (default and maximum: when the session starts)
+    pub until: Option<DateTime<Utc>>,
+    /// Event kinds (empty: all)
+    pub event_kinds: Vec<String>,
+    pub tenant: Option<String>,
+    /// Operator starting the session (audited by the service)
+    pub requested_by: String,
+    /// Event lines per response
+    pub page_limit: u64,
+    pub response_timeout: Duration,
+}
+
+impl ExportRequest {
+    pub fn new(since: DateTime<Utc>, requested_by: impl Into<String>) -> Self {
+        Self {
+            since,
+            until: None,
+            event_kinds: Vec::new(),
+            tenant: None,
+            requested_by: requested_by.into(),
+            page_limit: DEFAULT_PAGE_LIMIT,
+            response_timeout: DEFAULT_RESPONSE_TIMEOUT,
+        }
+    }
+
+    fn session_query(&self) -> Vec<(String, String)> {
+        let mut query = vec![
+            ("since".to_string(), self.since.to_rfc3339_opts(SecondsFormat::Micros, true)),
+            ("requested_by".to_string(), self.requested_by.clone()),
+            ("limit".to_string(), self.page_limit.to_string()),
+        ];
+        if let Some(until) = self.until {
+            query.push(("until".to_string(), until.to_rfc3339_opts(SecondsFormat::Micros, true)));
+        }
+        if !self.event_kinds.is_empty() {
+            query.push(("event_kind".to_string(), self.event_kinds.join(",")));
+        }
+        if let Some(tenant) = &self.tenant {
+            query.push(("tenant".to_string(), tenant.clone()));
+        }
+        query
+    }
+}
+
+/// Events of one export session, across as many responses as it takes.
+pub struct ExportStream<'a> {
+    client: &'a RansomEyeClient,
+    request: ExportRequest,
+    /// Resume position: the cursor of the last event read, or the end line's next_cursor
+    cursor: Option<String>,
+    response: Option<Response>,
+    pending: Vec<u8>,
+    events: VecDeque<ExportedEvent>,
+    session_id: Option<String>,
+    exported: u64,
+    /// Responses in a row that ended early without an event
+    stalls: u32,
+    read_in_response: bool,
+    finished: bool,
+}
+
+impl<'a> ExportStream<'a> {
+    pub(crate) fn new(client: &'a RansomEyeClient, request: ExportRequest) -> Self {
+        Self {
+            client,
+            request,
+            cursor: None,
+            response: None,
+            pending: Vec::new(),
+            events: VecDeque::new(),
+            session_id: None,
+            exported: 0,
+            stalls: 0,
+            read_in_response: false,
+            finished: false,
+        }
+    }
+
+    /// Next event of the session, None once it is exhausted
+    pub async fn next_event(&mut self) -> Result<Option<ExportedEvent>, ClientError> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                self.exported += 1;
+                return Ok(Some(event));
+            }
+            if self.finished {
+                return Ok(None);
+            }
+            if self.response.is_none() {
+                self.open().await?;
+            }
+            let Some(response) = self.response.as_mut() else { continue };
+            match response.chunk().await {
+                Ok(Some(chunk)) => {
+                    self.pending.extend_from_slice(&chunk);
+                    self.take_lines()?;
+                }
+                Ok(None) => self.interrupted("response ended without an end line".to_string())?,
+                Err(e) => self.interrupted(e.to_string())?,
+            }
+        }
+    }
+
+    /// Every remaining event
+    pub async fn collect_all(mut self) -> Result<Vec<ExportedEvent>, ClientError> {
+        let mut events = Vec::new();
+        while let Some(event) = self.next_event().await? {
+            events.push(event);
+        }
+        Ok(events)
+    }
+
+    /// Session id (X-Export-Session), once the first response arrived
+    pub fn session_id(&self) -> Option<&str> {
+        self.session_id.as_deref()
+    }
+
+    /// Cursor to resume this session from later (None before the first event)
+    pub fn cursor(&self) -> Option<&str> {
+        self.cursor.as_deref()
+    }
+
+    /// Events returned so far
+    pub fn exported(&self) -> u64 {
+        self.exported
+    }
+
+    async fn open(&mut self) -> Result<(), ClientError> {
+        let query = match &self.cursor {
+            Some(cursor) => vec![
+                ("cursor".to_string(), cursor.clone()),
+                ("limit".to_string(), self.request.page_limit.to_string()),
+            ],
+            None => self.request.session_query(),
+        };
+        let timeout = self.request.response_timeout;
+        let response = self
+            .client
+            .send(Auth::Admin, Method::GET, "/admin/export/events", true, |req| req.query(&query).timeout(timeout))
+            .await?;
+        if let Some(session) = response.headers().get("x-export-session").and_then(|v| v.to_str().ok()) {
+            self.session_id = Some(session.to_string());
+        }
+        self.response = Some(response);
+        self.pending.clear();
+        self.read_in_response = false;
+        Ok(())
+    }
+
+    fn take_lines(&mut self) -> Result<(), ClientError> {
+        while let Some(pos) = self.pending.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=pos).collect();
+            if line.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+            let parsed: ExportLine = serde_json::from_slice(&line)
+                .map_err(|e| ClientError::Decode(format!("export line: {}", e)))?;
+            match parsed {
+                ExportLine::Event { cursor, event } => {
+                    self.cursor = Some(cursor);
+                    self.events.push_back(*event);
+                    self.read_in_response = true;
+                    self.stalls = 0;
+                }
+                ExportLine::End { next_cursor, exported } => {
+                    debug!("Export response complete: {} event(s), more={}", exported, next_cursor.is_some());
+                    self.response = None;
+                    match next_cursor {
+                        Some(next) => self.cursor = Some(next),
+                        None => self.finished = true,
+                    }
+                    return Ok(());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The response ended early: resume from the last cursor, bounded when nothing arrives
+    fn interrupted(&mut self, reason: String) -> Result<(), ClientError> {
+        self.response = None;
+        if !self.read_in_response {
+            self.stalls += 1;
+            if self.stalls >= self.client.retry_policy().max_attempts.max(1) {
+                return Err(ClientError::Interrupted(reason));
+            }
+        }
+        warn!("Export response interrupted ({}), resuming at {:?}", reason, self.cursor);
+        Ok(())
+    }
+}
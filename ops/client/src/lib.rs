@@ -0,0 +1,20 @@
+// Path and File Name : /home/ransomeye/rebuild/ops/client/src/lib.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: RansomEye API client SDK - typed ingest, fleet, incident and export calls with agent / operator authentication, retries, list pagination and resumable data export for third-party integrations
+
+pub mod client;
+pub mod errors;
+pub mod export;
+pub mod list;
+pub mod models;
+pub mod retry;
+
+pub use client::{AdminCredentials, RansomEyeClient};
+pub use errors::ClientError;
+pub use export::{ExportRequest, ExportStream};
+pub use list::{FilterOp, ListParams, Pager};
+pub use models::{
+    CloseIncident, DiskEncryption, Disposition, ExportStats, ExportedEvent, FleetOverview, HostInventory, IncidentClosure,
+    IngestResponse, Page, SignedEvent,
+};
+pub use retry::RetryPolicy;
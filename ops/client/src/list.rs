@@ -0,0 +1,165 @@
+// Path and File Name : /home/ransomeye/rebuild/ops/client/src/list.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: List endpoint parameters (page size, filter[...] operators, sparse fieldsets) and the cursor-following pager over pages and items
+
+use std::collections::VecDeque;
+
+use serde::de::DeserializeOwned;
+
+use crate::client::{Auth, RansomEyeClient};
+use crate::errors::ClientError;
+use crate::models::Page;
+
+/// Largest page size the services accept
+pub const MAX_PAGE_SIZE: usize = 500;
+
+/// Comparison of a filter[...] parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterOp {
+    Eq,
+    Ne,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    /// Any of comma-separated values
+    In,
+}
+
+impl FilterOp {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FilterOp::Eq => "eq",
+            FilterOp::Ne => "ne",
+            FilterOp::Gt => "gt",
+            FilterOp::Gte => "gte",
+            FilterOp::Lt => "lt",
+            FilterOp::Lte => "lte",
+            FilterOp::In => "in",
+        }
+    }
+}
+
+/// Query of a list endpoint. Fields an endpoint does not filter or select on are refused by
+/// the service with a 400.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ListParams {
+    /// Page size (1-500; service default 50)
+    page_size: Option<usize>,
+    filters: Vec<(String, FilterOp, String)>,
+    fields: Vec<String>,
+}
+
+impl ListParams {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn page_size(mut self, size: usize) -> Self {
+        self.page_size = Some(size.clamp(1, MAX_PAGE_SIZE));
+        self
+    }
+
+    /// filter[field]=value
+    pub fn filter(self, field: &str, value: impl Into<String>) -> Self {
+        self.filter_op(field, FilterOp::Eq, value)
+    }
+
+    /// filter[field][op]=value (dotted paths address nested fields)
+    pub fn filter_op(mut self, field: &str, op: FilterOp, value: impl Into<String>) -> Self {
+        self.filters.push((field.to_string(), op, value.into()));
+        self
+    }
+
+    /// Sparse fieldset: only these top-level fields per item
+    pub fn fields(mut self, fields: &[&str]) -> Self {
+        self.fields = fields.iter().map(|f| f.to_string()).collect();
+        self
+    }
+
+    /// Query pairs of one page request
+    pub fn to_query(&self, cursor: Option<&str>) -> Vec<(String, String)> {
+        let mut query = Vec::new();
+        if let Some(size) = self.page_size {
+            query.push(("limit".to_string(), size.to_string()));
+        }
+        if let Some(cursor) = cursor {
+            query.push(("cursor".to_string(), cursor.to_string()));
+        }
+        for (field, op, value) in &self.filters {
+            let key = match op {
+                FilterOp::Eq => format!("filter[{}]", field),
+                op => format!("filter[{}][{}]", field, op.as_str()),
+            };
+            query.push((key, value.clone()));
+        }
+        if !self.fields.is_empty() {
+            query.push(("fields".to_string(), self.fields.join(",")));
+        }
+        query
+    }
+}
+
+/// Follows next_cursor through a list endpoint. Pages stay stable while rows are added, so a
+/// walk sees every item that existed when it started exactly once.
+pub struct Pager<'a, T> {
+    client: &'a RansomEyeClient,
+    path: String,
+    params: ListParams,
+    cursor: Option<String>,
+    buffer: VecDeque<T>,
+    done: bool,
+    total_estimate: Option<u64>,
+}
+
+impl<'a, T: DeserializeOwned> Pager<'a, T> {
+    pub(crate) fn new(client: &'a RansomEyeClient, path: &str, params: ListParams) -> Self {
+        Self {
+            client,
+            path: path.to_string(),
+            params,
+            cursor: None,
+            buffer: VecDeque::new(),
+            done: false,
+            total_estimate: None,
+        }
+    }
+
+    /// Next page, None after the last one
+    pub async fn next_page(&mut self) -> Result<Option<Page<T>>, ClientError> {
+        if self.done {
+            return Ok(None);
+        }
+        let query = self.params.to_query(self.cursor.as_deref());
+        let page: Page<T> = self.client.get_json(Auth::Admin, &self.path, &query).await?;
+        self.total_estimate = Some(page.total_estimate);
+        self.cursor = page.next_cursor.clone();
+        self.done = self.cursor.is_none();
+        Ok(Some(page))
+    }
+
+    /// Next item across pages, None after the last one
+    pub async fn next_item(&mut self) -> Result<Option<T>, ClientError> {
+        while self.buffer.is_empty() {
+            match self.next_page().await? {
+                Some(page) => self.buffer.extend(page.data),
+                None => return Ok(None),
+            }
+        }
+        Ok(self.buffer.pop_front())
+    }
+
+    /// Every remaining item
+    pub async fn collect_all(mut self) -> Result<Vec<T>, ClientError> {
+        let mut items = Vec::new();
+        while let Some(item) = self.next_item().await? {
+            items.push(item);
+        }
+        Ok(items)
+    }
+
+    /// Items matching the filters according to the last page served
+    pub fn total_estimate(&self) -> Option<u64> {
+        self.total_estimate
+    }
+}
@@ -0,0 +1,181 @@
+// Path and File Name : /home/ransomeye/rebuild/ops/client/src/models.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Typed request and response models of the RansomEye ingest, fleet, incident and export APIs
+
+/*
+ * API Models
+ *
+ * Field names and shapes follow the services' responses (see the OpenAPI contract at
+ * /openapi.json). Unknown fields are ignored and unknown enum values map to an Unknown variant,
+ * so a newer service does not break an older client.
+ */
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use uuid::Uuid;
+
+/// Signed telemetry event as posted to /ingest/linux and /ingest/dpi. The producer signs the
+/// envelope and computes payload_hash; the client sends them unchanged.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignedEvent {
+    pub envelope: JsonValue,
+    /// SHA-256 hex of the producer's canonical envelope bytes
+    pub payload_hash: String,
+    /// Base64 Ed25519 signature
+    pub signature: String,
+    pub signer_id: String,
+}
+
+/// Answer to an accepted ingest request.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct IngestResponse {
+    /// "ok" (stored) or "quarantined" (held under an identity conflict)
+    pub status: String,
+    pub message_id: String,
+}
+
+impl IngestResponse {
+    pub fn is_quarantined(&self) -> bool {
+        self.status == "quarantined"
+    }
+}
+
+/// One page of a list endpoint.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Page<T> {
+    pub data: Vec<T>,
+    pub next_cursor: Option<String>,
+    pub total_estimate: u64,
+}
+
+/// Disk encryption status of a host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiskEncryption {
+    /// Root filesystem on dm-crypt
+    Encrypted,
+    /// dm-crypt volumes, root filesystem not on one
+    Partial,
+    None,
+    #[serde(other)]
+    Unknown,
+}
+
+/// Security tooling installed on a host.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SecurityTool {
+    pub name: String,
+    /// edr, antivirus, hids, fim, audit, runtime, mac
+    pub kind: String,
+}
+
+/// Latest host inventory an agent reported (GET /admin/fleet/inventory).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct HostInventory {
+    pub agent_id: Uuid,
+    pub hostname: Option<String>,
+    pub os_name: Option<String>,
+    pub os_version: Option<String>,
+    pub kernel_release: String,
+    pub kernel_version: Option<String>,
+    pub arch: String,
+    pub cpu_model: Option<String>,
+    pub cpu_cores: u32,
+    pub memory_total_bytes: u64,
+    #[serde(default)]
+    pub security_tools: Vec<SecurityTool>,
+    pub disk_encryption: DiskEncryption,
+    #[serde(default)]
+    pub encrypted_volumes: Vec<String>,
+    /// Envelope event_id of the report
+    pub source_event_id: String,
+    pub collected_at: DateTime<Utc>,
+    pub received_at: DateTime<Utc>,
+}
+
+/// Agents of one agent type.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct AgentTypeCount {
+    pub agent_type: String,
+    pub agents: u64,
+    /// Not decommissioned and seen within the reporting window
+    pub reporting: u64,
+}
+
+/// Agent counts across the fleet.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct FleetSummary {
+    pub agents: u64,
+    pub decommissioned: u64,
+    pub reporting: u64,
+    pub by_type: Vec<AgentTypeCount>,
+}
+
+/// GET /admin/dashboard/fleet-summary
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct FleetOverview {
+    pub as_of: DateTime<Utc>,
+    pub reporting_window_minutes: i64,
+    pub fleet: FleetSummary,
+}
+
+/// Analyst verdict on a closed incident.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Disposition {
+    TruePositive,
+    FalsePositive,
+}
+
+/// POST /admin/incidents/close
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CloseIncident {
+    pub incident_id: Uuid,
+    pub disposition: Disposition,
+    pub closed_by: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct IncidentClosure {
+    pub incident_id: Uuid,
+    pub disposition: Disposition,
+    /// Detections naming the incident when it was closed
+    pub detections: i64,
+    pub closed_at: DateTime<Utc>,
+}
+
+/// One normalized event of a data export.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ExportedEvent {
+    pub normalized_event_id: Uuid,
+    pub raw_event_id: Uuid,
+    pub normalized_at: DateTime<Utc>,
+    pub observed_at: Option<DateTime<Utc>>,
+    pub source_type: String,
+    pub source_agent_id: Option<Uuid>,
+    pub tenant_id: Option<String>,
+    pub event_kind: String,
+    pub event_subkind: Option<String>,
+    pub severity: String,
+    pub attributes: Option<JsonValue>,
+}
+
+/// One NDJSON line of an export response.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExportLine {
+    Event { cursor: String, event: Box<ExportedEvent> },
+    /// Last line of a complete response; next_cursor is None once the session is exhausted
+    End { next_cursor: Option<String>, exported: u64 },
+}
+
+/// GET /admin/export/stats
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ExportStats {
+    pub active_streams: usize,
+    pub sessions_started: u64,
+    pub rows_exported: u64,
+    pub refused: u64,
+}
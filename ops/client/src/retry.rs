@@ -0,0 +1,78 @@
+// Path and File Name : /home/ransomeye/rebuild/ops/client/src/retry.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Retry policy of the RansomEye API client - bounded attempts, jittered exponential backoff, Retry-After honoured up to the backoff ceiling
+
+use std::time::Duration;
+
+use rand::Rng;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+
+/// When and how often a request is repeated.
+///
+/// Transport errors and 502/503/504 are retried for idempotent requests. 429 and 503 with a
+/// Retry-After header mean the service refused the request before doing anything, so they are
+/// retried for every request, after the advertised delay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts per request including the first (1 = no retries)
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    /// Ceiling for backoff and for Retry-After
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// No retries
+    pub fn none() -> Self {
+        Self { max_attempts: 1, ..Self::default() }
+    }
+
+    /// Jittered exponential backoff before retry number `retry` (0-based): uniform in [ceiling/2, ceiling]
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let ceiling = self
+            .base_delay
+            .saturating_mul(1u32.checked_shl(retry).unwrap_or(u32::MAX))
+            .min(self.max_delay);
+        let ceiling_ms = ceiling.as_millis() as u64;
+        if ceiling_ms == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_millis(rand::thread_rng().gen_range(ceiling_ms / 2..=ceiling_ms))
+    }
+
+    /// Delay before retrying a response with this status, None if it is not retried
+    pub(crate) fn delay_for(&self, status: StatusCode, headers: &HeaderMap, retry: u32, idempotent: bool) -> Option<Duration> {
+        let retry_after = retry_after(headers).map(|d| d.min(self.max_delay));
+        match status {
+            StatusCode::TOO_MANY_REQUESTS => Some(retry_after.unwrap_or_else(|| self.backoff(retry))),
+            StatusCode::SERVICE_UNAVAILABLE if retry_after.is_some() => retry_after,
+            StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT if idempotent => {
+                Some(self.backoff(retry))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Retry-After in delta-seconds (the only form the services send)
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(Duration::from_secs)
+}
@@ -0,0 +1,488 @@
+// Path and File Name : /home/ransomeye/rebuild/ops/client/tests/client_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for the API client against the services' own wire handling - ingest signed event parsing, admin key checks, list pagination and filters, incident closure and resumable NDJSON export
+
+/*
+ * The test service answers with the ingest crate's response types and runs its list query,
+ * export parameter and cursor code, so a field renamed on either side, a changed pagination or
+ * cursor contract fails here. Storage is in memory; no database is needed.
+ */
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use axum::body::{Body, Bytes};
+use axum::extract::{Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Json, Response};
+use axum::routing::{get, post};
+use axum::Router;
+use chrono::{Duration as ChronoDuration, Utc};
+use parking_lot::Mutex;
+use uuid::Uuid;
+
+use ingest::dashboard_cache::FleetOverview as ServiceFleetOverview;
+use ingest::export::{ExportLine as ServiceExportLine, ExportParams, ExportStats as ServiceExportStats, ExportedEvent as ServiceExportedEvent};
+use ingest::http_list::{ListQuery, ListQueryRejection, ListSpec};
+use ingest::http_rule_metrics_admin::{CloseIncidentRequest, CloseIncidentResponse};
+use ingest::http_runtime_admin::AdminKey;
+use ingest::http_server::IngestResponse as ServiceIngestResponse;
+use ingest::protocol::signed_event::SignedEventRef;
+use ingest::storage::{AgentTypeCount, DiskEncryption as ServiceDiskEncryption, FleetSummary, HostInventoryRecord, SecurityTool};
+
+use ransomeye_client::{
+    ClientError, CloseIncident, DiskEncryption, Disposition, ExportRequest, FilterOp, ListParams, RansomEyeClient, RetryPolicy,
+    SignedEvent,
+};
+
+const ADMIN_KEY: &str = "0123456789abcdef-admin";
+const OPERATOR_TOKEN: &str = "operator-session-token";
+const AGENT_TOKEN: &str = "agent-token-7f3a";
+
+const INVENTORY_LIST: ListSpec = ListSpec {
+    filterable: &["agent_id", "hostname", "kernel_release", "disk_encryption"],
+    selectable: &["agent_id", "hostname", "kernel_release", "disk_encryption", "collected_at"],
+};
+
+struct TestService {
+    admin_key: AdminKey,
+    inventory: Vec<HostInventoryRecord>,
+    events: Vec<ServiceExportedEvent>,
+    /// Ingest requests answered 503 + Retry-After before one is accepted
+    unavailable: AtomicUsize,
+    ingest_requests: AtomicUsize,
+    close_requests: AtomicUsize,
+    export_requests: AtomicUsize,
+    /// Cut the next export response short (no end line)
+    truncate_export: AtomicBool,
+    closed: Mutex<HashSet<Uuid>>,
+}
+
+type Svc = Arc<TestService>;
+
+fn error_body(status: StatusCode, reason: &str) -> Response {
+    (status, Json(serde_json::json!({ "error": reason }))).into_response()
+}
+
+fn admin(svc: &TestService, headers: &HeaderMap) -> Result<(), StatusCode> {
+    let bearer = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
+    if bearer == Some(&format!("Bearer {}", OPERATOR_TOKEN)) {
+        return Ok(());
+    }
+    svc.admin_key.check(headers)
+}
+
+async fn ingest_linux(State(svc): State<Svc>, headers: HeaderMap, body: Bytes) -> Response {
+    svc.ingest_requests.fetch_add(1, Ordering::SeqCst);
+    if headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok()) != Some(&format!("Bearer {}", AGENT_TOKEN)) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    if svc.unavailable.load(Ordering::SeqCst) > 0 {
+        svc.unavailable.fetch_sub(1, Ordering::SeqCst);
+        return (StatusCode::SERVICE_UNAVAILABLE, [(header::RETRY_AFTER, "0")]).into_response();
+    }
+    let Ok(payload) = SignedEventRef::parse(&body) else {
+        return error_body(StatusCode::BAD_REQUEST, "malformed signed event");
+    };
+    let Ok(envelope) = payload.envelope() else {
+        return error_body(StatusCode::BAD_REQUEST, "malformed envelope");
+    };
+    Json(ServiceIngestResponse { status: "ok".to_string(), message_id: envelope.event_id.to_string() }).into_response()
+}
+
+async fn fleet_inventory(
+    State(svc): State<Svc>,
+    headers: HeaderMap,
+    query: Result<ListQuery, ListQueryRejection>,
+) -> Response {
+    if let Err(refused) = admin(&svc, &headers) {
+        return refused.into_response();
+    }
+    let query = match query {
+        Ok(query) => query,
+        Err(rejection) => return rejection.into_response(),
+    };
+    match query.paginate(&INVENTORY_LIST, svc.inventory.clone(), |h| {
+        format!("{}|{}", h.hostname.as_deref().unwrap_or_default(), h.agent_id)
+    }) {
+        Ok(page) => Json(page).into_response(),
+        Err(rejection) => rejection.into_response(),
+    }
+}
+
+async fn fleet_summary(State(svc): State<Svc>, headers: HeaderMap) -> Response {
+    if let Err(refused) = admin(&svc, &headers) {
+        return refused.into_response();
+    }
+    Json(ServiceFleetOverview {
+        as_of: Utc::now(),
+        reporting_window_minutes: 15,
+        fleet: FleetSummary {
+            agents: 7,
+            decommissioned: 1,
+            reporting: 5,
+            by_type: vec![AgentTypeCount { agent_type: "linux".to_string(), agents: 7, reporting: 5 }],
+        },
+    })
+    .into_response()
+}
+
+async fn close_incident(State(svc): State<Svc>, headers: HeaderMap, Json(req): Json<CloseIncidentRequest>) -> Response {
+    svc.close_requests.fetch_add(1, Ordering::SeqCst);
+    if let Err(refused) = admin(&svc, &headers) {
+        return refused.into_response();
+    }
+    if !svc.closed.lock().insert(req.incident_id) {
+        return StatusCode::CONFLICT.into_response();
+    }
+    Json(CloseIncidentResponse { incident_id: req.incident_id, disposition: req.disposition, detections: 3, closed_at: Utc::now() })
+        .into_response()
+}
+
+/// Same session, cursor and line contract as GET /admin/export/events, over the in-memory rows
+async fn export_events(
+    State(svc): State<Svc>,
+    headers: HeaderMap,
+    params: Result<Query<ExportParams>, axum::extract::rejection::QueryRejection>,
+) -> Response {
+    svc.export_requests.fetch_add(1, Ordering::SeqCst);
+    if let Err(refused) = admin(&svc, &headers) {
+        return refused.into_response();
+    }
+    let Query(params) = match params {
+        Ok(params) => params,
+        Err(e) => return error_body(StatusCode::BAD_REQUEST, &e.body_text()),
+    };
+    let start = match params.start(Utc::now()) {
+        Ok(start) => start,
+        Err(reason) => return error_body(StatusCode::BAD_REQUEST, &reason),
+    };
+    let mut cursor = start.cursor;
+    let f = cursor.filter.clone();
+    let mut rows: Vec<&ServiceExportedEvent> = svc
+        .events
+        .iter()
+        .filter(|e| e.normalized_at >= f.since && e.normalized_at < f.until)
+        .filter(|e| f.event_kinds.is_empty() || f.event_kinds.contains(&e.event_kind))
+        .filter(|e| f.tenant.is_none() || e.tenant_id == f.tenant)
+        .filter(|e| cursor.after.is_none_or(|after| (e.normalized_at, e.normalized_event_id) > after))
+        .collect();
+    rows.sort_by_key(|e| (e.normalized_at, e.normalized_event_id));
+
+    let limit = start.limit as usize;
+    let more = rows.len() > limit;
+    let truncate = svc.truncate_export.swap(false, Ordering::SeqCst);
+    let served = if truncate { limit.min(rows.len()) / 2 } else { limit.min(rows.len()) };
+    let mut body = String::new();
+    for event in rows.into_iter().take(served) {
+        cursor = cursor.after(event.normalized_at, event.normalized_event_id);
+        body.push_str(&ServiceExportLine::Event { cursor: cursor.encode(), event: Box::new(event.clone()) }.to_ndjson());
+    }
+    if !truncate {
+        body.push_str(&ServiceExportLine::End { next_cursor: more.then(|| cursor.encode()), exported: served as u64 }.to_ndjson());
+    }
+    (
+        [(header::CONTENT_TYPE, "application/x-ndjson".to_string()), (header::HeaderName::from_static("x-export-session"), cursor.session_id.to_string())],
+        Body::from(body),
+    )
+        .into_response()
+}
+
+async fn export_stats(State(svc): State<Svc>, headers: HeaderMap) -> Response {
+    if let Err(refused) = admin(&svc, &headers) {
+        return refused.into_response();
+    }
+    Json(ServiceExportStats { active_streams: 0, sessions_started: 2, rows_exported: 40, refused: 1 }).into_response()
+}
+
+fn inventory() -> Vec<HostInventoryRecord> {
+    (0..7)
+        .map(|i| HostInventoryRecord {
+            agent_id: Uuid::new_v4(),
+            hostname: Some(format!("host-{:02}", 7 - i)),
+            os_name: Some("Ubuntu".to_string()),
+            os_version: Some("24.04".to_string()),
+            kernel_release: if i % 2 == 0 { "6.8.0-45-generic".to_string() } else { "5.15.0-119-generic".to_string() },
+            kernel_version: None,
+            arch: "x86_64".to_string(),
+            cpu_model: None,
+            cpu_cores: 8,
+            memory_total_bytes: 16 << 30,
+            security_tools: vec![SecurityTool { name: "auditd".to_string(), kind: "audit".to_string() }],
+            disk_encryption: if i < 5 { ServiceDiskEncryption::Encrypted } else { ServiceDiskEncryption::None },
+            encrypted_volumes: Vec::new(),
+            source_event_id: format!("evt-{}", i),
+            collected_at: Utc::now() - ChronoDuration::minutes(5),
+            received_at: Utc::now(),
+        })
+        .collect()
+}
+
+fn events(n: i64) -> Vec<ServiceExportedEvent> {
+    let base = Utc::now() - ChronoDuration::minutes(30);
+    (0..n)
+        .map(|i| ServiceExportedEvent {
+            normalized_event_id: Uuid::new_v4(),
+            raw_event_id: Uuid::new_v4(),
+            normalized_at: base + ChronoDuration::seconds(i),
+            observed_at: Some(base),
+            source_type: "linux_agent".to_string(),
+            source_agent_id: Some(Uuid::new_v4()),
+            tenant_id: Some(if i % 5 == 0 { "globex" } else { "acme" }.to_string()),
+            event_kind: "process".to_string(),
+            event_subkind: Some("exec".to_string()),
+            severity: "low".to_string(),
+            attributes: Some(serde_json::json!({ "seq": i })),
+        })
+        .collect()
+}
+
+async fn serve() -> (String, Svc) {
+    let svc = Arc::new(TestService {
+        admin_key: AdminKey::new(Some(ADMIN_KEY.as_bytes().to_vec())),
+        inventory: inventory(),
+        events: events(25),
+        unavailable: AtomicUsize::new(0),
+        ingest_requests: AtomicUsize::new(0),
+        close_requests: AtomicUsize::new(0),
+        export_requests: AtomicUsize::new(0),
+        truncate_export: AtomicBool::new(false),
+        closed: Mutex::new(HashSet::new()),
+    });
+    let app = Router::new()
+        .route("/ingest/linux", post(ingest_linux))
+        .route("/admin/fleet/inventory", get(fleet_inventory))
+        .route("/admin/dashboard/fleet-summary", get(fleet_summary))
+        .route("/admin/incidents/close", post(close_incident))
+        .route("/admin/export/events", get(export_events))
+        .route("/admin/export/stats", get(export_stats))
+        .with_state(svc.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (format!("http://{}/", addr), svc)
+}
+
+fn fast_retry() -> RetryPolicy {
+    RetryPolicy { max_attempts: 3, base_delay: std::time::Duration::from_millis(1), max_delay: std::time::Duration::from_millis(5) }
+}
+
+fn signed_event(event_id: Uuid) -> SignedEvent {
+    SignedEvent {
+        envelope: serde_json::json!({
+            "event_id": event_id.to_string(),
+            "timestamp": "2026-10-16T08:00:00Z",
+            "component": "linux_agent",
+            "component_id": "host-7f3a",
+            "event_type": "process",
+            "sequence": 1,
+            "data": {},
+        }),
+        payload_hash: "00".repeat(32),
+        signature: "c2lnbmF0dXJl".to_string(),
+        signer_id: "host-7f3a".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_ingest_sends_signed_event_with_agent_token() {
+    let (url, svc) = serve().await;
+    let client = RansomEyeClient::new(&url).unwrap().with_agent_token(AGENT_TOKEN);
+    let event_id = Uuid::new_v4();
+    let accepted = client.ingest_linux(&signed_event(event_id)).await.unwrap();
+    assert_eq!(accepted.status, "ok");
+    assert!(!accepted.is_quarantined());
+    assert_eq!(accepted.message_id, event_id.to_string());
+    assert_eq!(svc.ingest_requests.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_missing_credentials_fail_before_sending() {
+    let (url, svc) = serve().await;
+    let client = RansomEyeClient::new(&url).unwrap();
+    let err = client.ingest_linux(&signed_event(Uuid::new_v4())).await.unwrap_err();
+    assert!(matches!(err, ClientError::MissingCredentials(_)), "{}", err);
+    assert!(matches!(client.fleet_summary().await.unwrap_err(), ClientError::MissingCredentials(_)));
+    assert_eq!(svc.ingest_requests.load(Ordering::SeqCst), 0);
+
+    assert!(RansomEyeClient::new("ftp://core.example.com").is_err());
+    assert!(RansomEyeClient::new("not a url").is_err());
+}
+
+#[tokio::test]
+async fn test_retry_after_is_honoured_and_bounded() {
+    let (url, svc) = serve().await;
+    let client = RansomEyeClient::new(&url).unwrap().with_agent_token(AGENT_TOKEN).with_retry(fast_retry());
+    svc.unavailable.store(2, Ordering::SeqCst);
+    client.ingest_linux(&signed_event(Uuid::new_v4())).await.unwrap();
+    assert_eq!(svc.ingest_requests.load(Ordering::SeqCst), 3);
+
+    svc.unavailable.store(5, Ordering::SeqCst);
+    let err = client.ingest_linux(&signed_event(Uuid::new_v4())).await.unwrap_err();
+    assert_eq!(err.status(), Some(503));
+    assert_eq!(svc.ingest_requests.load(Ordering::SeqCst), 6);
+}
+
+#[tokio::test]
+async fn test_refusals_are_not_retried() {
+    let (url, svc) = serve().await;
+    let client = RansomEyeClient::new(&url).unwrap().with_agent_token("revoked").with_retry(fast_retry());
+    let err = client.ingest_linux(&signed_event(Uuid::new_v4())).await.unwrap_err();
+    assert_eq!(err.status(), Some(401));
+    assert_eq!(svc.ingest_requests.load(Ordering::SeqCst), 1);
+
+    let mut malformed = signed_event(Uuid::new_v4());
+    malformed.envelope = serde_json::json!("not an envelope");
+    let client = RansomEyeClient::new(&url).unwrap().with_agent_token(AGENT_TOKEN);
+    match client.ingest_linux(&malformed).await.unwrap_err() {
+        ClientError::Api { status, message } => {
+            assert_eq!(status, 400);
+            assert_eq!(message, "malformed envelope");
+        }
+        other => panic!("unexpected {}", other),
+    }
+
+    let wrong_key = RansomEyeClient::new(&url).unwrap().with_admin_key("wrong-key-wrong-key");
+    assert_eq!(wrong_key.fleet_summary().await.unwrap_err().status(), Some(401));
+}
+
+#[tokio::test]
+async fn test_fleet_inventory_follows_cursors() {
+    let (url, _svc) = serve().await;
+    let client = RansomEyeClient::new(&url).unwrap().with_admin_key(ADMIN_KEY);
+
+    let mut pager = client.fleet_inventory(ListParams::new().page_size(3));
+    let first = pager.next_page().await.unwrap().unwrap();
+    assert_eq!(first.data.len(), 3);
+    assert_eq!(first.total_estimate, 7);
+    assert!(first.next_cursor.is_some());
+
+    let hosts = client.fleet_inventory(ListParams::new().page_size(3)).collect_all().await.unwrap();
+    let names: Vec<String> = hosts.iter().map(|h| h.hostname.clone().unwrap()).collect();
+    assert_eq!(names, (1..=7).map(|i| format!("host-{:02}", i)).collect::<Vec<_>>());
+    assert_eq!(hosts[0].security_tools[0].kind, "audit");
+
+    let uncovered = client
+        .fleet_inventory(ListParams::new().filter_op("disk_encryption", FilterOp::Ne, "encrypted"))
+        .collect_all()
+        .await
+        .unwrap();
+    assert_eq!(uncovered.len(), 2);
+    assert!(uncovered.iter().all(|h| h.disk_encryption == DiskEncryption::None));
+
+    let old_kernels = client
+        .fleet_inventory(ListParams::new().filter("kernel_release", "5.15.0-119-generic"))
+        .collect_all()
+        .await
+        .unwrap();
+    assert_eq!(old_kernels.len(), 3);
+}
+
+#[tokio::test]
+async fn test_list_fields_and_unsupported_filters() {
+    let (url, _svc) = serve().await;
+    let client = RansomEyeClient::new(&url).unwrap().with_admin_key(ADMIN_KEY);
+
+    let sparse: Vec<serde_json::Value> = client
+        .list("/admin/fleet/inventory", ListParams::new().fields(&["hostname", "kernel_release"]))
+        .collect_all()
+        .await
+        .unwrap();
+    assert_eq!(sparse.len(), 7);
+    assert_eq!(sparse[0].as_object().unwrap().len(), 2);
+
+    let err = client
+        .fleet_inventory(ListParams::new().filter("cpu_model", "Xeon"))
+        .collect_all()
+        .await
+        .unwrap_err();
+    match err {
+        ClientError::Api { status, message } => {
+            assert_eq!(status, 400);
+            assert!(message.contains("cpu_model"), "{}", message);
+        }
+        other => panic!("unexpected {}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_fleet_summary_with_operator_token() {
+    let (url, _svc) = serve().await;
+    let client = RansomEyeClient::new(&url).unwrap().with_operator_token(OPERATOR_TOKEN);
+    let overview = client.fleet_summary().await.unwrap();
+    assert_eq!(overview.reporting_window_minutes, 15);
+    assert_eq!(overview.fleet.agents, 7);
+    assert_eq!(overview.fleet.by_type[0].agent_type, "linux");
+}
+
+#[tokio::test]
+async fn test_close_incident_once() {
+    let (url, svc) = serve().await;
+    let client = RansomEyeClient::new(&url).unwrap().with_admin_key(ADMIN_KEY).with_retry(fast_retry());
+    let close = CloseIncident {
+        incident_id: Uuid::new_v4(),
+        disposition: Disposition::FalsePositive,
+        closed_by: "analyst@example.com".to_string(),
+        reason: "Backup job renaming its own archives".to_string(),
+    };
+    let closure = client.close_incident(&close).await.unwrap();
+    assert_eq!(closure.incident_id, close.incident_id);
+    assert_eq!(closure.disposition, Disposition::FalsePositive);
+    assert_eq!(closure.detections, 3);
+
+    assert_eq!(client.close_incident(&close).await.unwrap_err().status(), Some(409));
+    assert_eq!(svc.close_requests.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_export_streams_every_event_across_responses() {
+    let (url, svc) = serve().await;
+    let client = RansomEyeClient::new(&url).unwrap().with_admin_key(ADMIN_KEY);
+    let mut request = ExportRequest::new(Utc::now() - ChronoDuration::hours(1), "analyst@example.com");
+    request.page_limit = 10;
+
+    let mut stream = client.export_events(request.clone());
+    let mut seqs = Vec::new();
+    while let Some(event) = stream.next_event().await.unwrap() {
+        seqs.push(event.attributes.unwrap()["seq"].as_i64().unwrap());
+    }
+    assert_eq!(seqs, (0..25).collect::<Vec<_>>());
+    assert_eq!(stream.exported(), 25);
+    assert!(stream.session_id().is_some());
+    assert_eq!(svc.export_requests.load(Ordering::SeqCst), 3);
+
+    request.tenant = Some("globex".to_string());
+    let globex = client.export_events(request).collect_all().await.unwrap();
+    assert_eq!(globex.len(), 5);
+    assert!(globex.iter().all(|e| e.tenant_id.as_deref() == Some("globex")));
+
+    let stats = client.export_stats().await.unwrap();
+    assert_eq!(stats.rows_exported, 40);
+}
+
+#[tokio::test]
+async fn test_export_resumes_interrupted_response() {
+    let (url, svc) = serve().await;
+    let client = RansomEyeClient::new(&url).unwrap().with_admin_key(ADMIN_KEY);
+    let mut request = ExportRequest::new(Utc::now() - ChronoDuration::hours(1), "analyst@example.com");
+    request.page_limit = 10;
+    svc.truncate_export.store(true, Ordering::SeqCst);
+
+    let events = client.export_events(request).collect_all().await.unwrap();
+    let ids: HashSet<Uuid> = events.iter().map(|e| e.normalized_event_id).collect();
+    assert_eq!(events.len(), 25);
+    assert_eq!(ids.len(), 25);
+    assert!(events.windows(2).all(|w| w[0].normalized_at < w[1].normalized_at));
+}
+
+#[tokio::test]
+async fn test_export_refuses_invalid_session() {
+    let (url, _svc) = serve().await;
+    let client = RansomEyeClient::new(&url).unwrap().with_admin_key(ADMIN_KEY);
+    let future = ExportRequest::new(Utc::now() + ChronoDuration::hours(1), "analyst@example.com");
+    assert_eq!(client.export_events(future).next_event().await.unwrap_err().status(), Some(400));
+
+    let anonymous = ExportRequest::new(Utc::now() - ChronoDuration::hours(1), " ");
+    assert_eq!(client.export_events(anonymous).next_event().await.unwrap_err().status(), Some(400));
+}
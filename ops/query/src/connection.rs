@@ -0,0 +1,188 @@
+// Path and File Name : /home/ransomeye/rebuild/ops/query/src/connection.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Read-only database session for the operator query tool - restricted login role (refused if it can write), read-only transactions, a statement timeout and the snapshot an incident export reads
+
+use chrono::{DateTime, Utc};
+use serde_json::Value as JsonValue;
+use tokio_postgres::types::ToSql;
+use tokio_postgres::{Client, IsolationLevel, NoTls, Transaction};
+
+use crate::errors::QueryError;
+use crate::incident::{self, IncidentQuery, IncidentRecords};
+use crate::investigation::Investigation;
+use crate::output::ResultSet;
+
+/// Tables the investigations and incident exports read; the login role must not be able to write any of them
+pub const READ_TABLES: [&str; 10] = [
+    "ransomeye.agents",
+    "ransomeye.normalized_events",
+    "ransomeye.linux_agent_telemetry",
+    "ransomeye.dpi_probe_telemetry",
+    "ransomeye.detection_results",
+    "ransomeye.raw_events",
+    "ransomeye.pcap_captures",
+    "ransomeye.memory_acquisitions",
+    "ransomeye.sandbox_submissions",
+    "ransomeye.legal_holds",
+];
+
+const DEFAULT_STATEMENT_TIMEOUT_SECS: u64 = 30;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryConfig {
+    pub host: String,
+    pub port: u16,
+    pub dbname: String,
+    /// Operator login role (RANSOMEYE_QUERY_DB_USER); never defaults to a service account
+    pub user: String,
+    pub password: Option<String>,
+    pub statement_timeout_secs: u64,
+}
+
+impl QueryConfig {
+    /// DB_HOST / DB_PORT / DB_NAME as for the services; the role comes from RANSOMEYE_QUERY_DB_USER
+    /// and RANSOMEYE_QUERY_DB_PASS.
+    pub fn from_env() -> Result<Self, QueryError> {
+        let port = match std::env::var("DB_PORT") {
+            Ok(v) => v.parse::<u16>().map_err(|_| QueryError::Config(format!("Invalid DB_PORT '{}'", v)))?,
+            Err(_) => 5432,
+        };
+        let user = std::env::var("RANSOMEYE_QUERY_DB_USER")
+            .ok()
+            .filter(|u| !u.trim().is_empty())
+            .ok_or_else(|| QueryError::Config("RANSOMEYE_QUERY_DB_USER is required (a login role granted ransomeye_ro)".to_string()))?;
+        let statement_timeout_secs = match std::env::var("RANSOMEYE_QUERY_STATEMENT_TIMEOUT_SECS") {
+            Ok(v) => v.parse::<u64>().ok().filter(|n| *n > 0).ok_or_else(|| {
+                QueryError::Config(format!("Invalid RANSOMEYE_QUERY_STATEMENT_TIMEOUT_SECS '{}' (expected integer > 0)", v))
+            })?,
+            Err(_) => DEFAULT_STATEMENT_TIMEOUT_SECS,
+        };
+        Ok(Self {
+            host: std::env::var("DB_HOST").unwrap_or_else(|_| "localhost".to_string()),
+            port,
+            dbname: std::env::var("DB_NAME").unwrap_or_else(|_| "ransomeye".to_string()),
+            user,
+            password: std::env::var("RANSOMEYE_QUERY_DB_PASS").ok(),
+            statement_timeout_secs,
+        })
+    }
+}
+
+/// Connection of a verified read-only role. Every investigation runs in a READ ONLY transaction.
+pub struct ReadOnlySession {
+    client: Client,
+    role: String,
+}
+
+impl ReadOnlySession {
+    pub async fn connect(cfg: &QueryConfig) -> Result<Self, QueryError> {
+        let mut pg = tokio_postgres::Config::new();
+        pg.host(&cfg.host).port(cfg.port).dbname(&cfg.dbname).user(&cfg.user);
+        if let Some(password) = &cfg.password {
+            pg.password(password);
+        }
+        let (client, connection) = pg.connect(NoTls).await.map_err(|e| QueryError::Connection(e.to_string()))?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                eprintln!("Database connection error: {}", e);
+            }
+        });
+
+        client
+            .batch_execute(&format!(
+                "SET search_path = ransomeye, public; SET default_transaction_read_only = on; SET statement_timeout = '{}s';",
+                cfg.statement_timeout_secs
+            ))
+            .await
+            .map_err(|e| QueryError::Connection(format!("session setup failed: {}", e)))?;
+
+        let role = verify_read_only(&client).await?;
+        Ok(Self { client, role })
+    }
+
+    pub fn role(&self) -> &str {
+        &self.role
+    }
+
+    pub async fn run(&mut self, investigation: &Investigation) -> Result<ResultSet, QueryError> {
+        investigation.validate()?;
+        let params = investigation.params();
+        let refs: Vec<&(dyn ToSql + Sync)> = params.iter().map(|p| p.as_ref() as &(dyn ToSql + Sync)).collect();
+        let sql = format!("SELECT row_to_json(q) FROM ({}) q", investigation.sql());
+
+        let tx = self.client.build_transaction().read_only(true).start().await?;
+        let rows = tx.query(&sql, &refs).await?;
+        tx.commit().await?;
+
+        let objects: Vec<JsonValue> = rows.iter().map(|r| r.get(0)).collect();
+        Ok(ResultSet::from_objects(investigation.columns(), &objects))
+    }
+
+    /// Every record of an incident, read from one snapshot (READ ONLY, REPEATABLE READ).
+    pub async fn incident_records(&mut self, query: &IncidentQuery) -> Result<IncidentRecords, QueryError> {
+        let tx = self
+            .client
+            .build_transaction()
+            .read_only(true)
+            .isolation_level(IsolationLevel::RepeatableRead)
+            .start()
+            .await?;
+        let id = &query.incident_id;
+        let mut records = IncidentRecords {
+            detections: objects(&tx, incident::DETECTIONS_SQL, &[id]).await?,
+            ..Default::default()
+        };
+        for sql in [incident::CAPTURES_SQL, incident::ACQUISITIONS_SQL, incident::SUBMISSIONS_SQL, incident::HOLDS_SQL] {
+            records.custody.extend(objects(&tx, sql, &[id]).await?);
+        }
+
+        // Without detections the window is empty
+        let epoch = DateTime::<Utc>::UNIX_EPOCH;
+        let (since, until) = records.event_window(query.context).unwrap_or((epoch, epoch));
+        // One row past the limit tells whether events were cut
+        let limit = query.max_events + 1;
+        records.events = objects(
+            &tx,
+            incident::EVENTS_SQL,
+            &[&records.agent_ids(), &since, &until, &records.trigger_event_ids(), &limit],
+        )
+        .await?;
+        if records.events.len() as i64 > query.max_events {
+            records.events.truncate(query.max_events as usize);
+            records.events_truncated = true;
+        }
+        records.raw_events = objects(&tx, incident::RAW_EVENTS_SQL, &[&records.raw_event_ids()]).await?;
+        tx.commit().await?;
+        Ok(records)
+    }
+}
+
+async fn objects(tx: &Transaction<'_>, sql: &str, params: &[&(dyn ToSql + Sync)]) -> Result<Vec<JsonValue>, QueryError> {
+    let rows = tx.query(&format!("SELECT row_to_json(q) FROM ({}) q", sql), params).await?;
+    Ok(rows.iter().map(|r| r.get(0)).collect())
+}
+
+/// FAIL-CLOSED: refuse superusers and roles with any write privilege on the tables read.
+async fn verify_read_only(client: &Client) -> Result<String, QueryError> {
+    let row = client
+        .query_one("SELECT current_user::text, r.rolsuper FROM pg_roles r WHERE r.rolname = current_user", &[])
+        .await?;
+    let role: String = row.get(0);
+    let superuser: bool = row.get(1);
+    if superuser {
+        return Err(QueryError::NotReadOnly { role, reason: "superuser".to_string() });
+    }
+
+    let tables: Vec<String> = READ_TABLES.iter().map(|t| t.to_string()).collect();
+    let writable = client
+        .query(
+            "SELECT t FROM unnest($1::text[]) t WHERE has_table_privilege(t, 'INSERT, UPDATE, DELETE, TRUNCATE')",
+            &[&tables],
+        )
+        .await?;
+    if !writable.is_empty() {
+        let names: Vec<String> = writable.iter().map(|r| r.get(0)).collect();
+        return Err(QueryError::NotReadOnly { role, reason: format!("can write {}", names.join(", ")) });
+    }
+    Ok(role)
+}
@@ -0,0 +1,47 @@
+// Path and File Name : /home/ransomeye/rebuild/ops/query/src/errors.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Error types for the operator query tool and incident export - a role with write access is refused (fail-closed)
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum QueryError {
+    #[error("Configuration error: {0}")]
+    Config(String),
+
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+
+    #[error("Database connection failed: {0}")]
+    Connection(String),
+
+    #[error("Role '{role}' is not read-only ({reason}); use a login role granted only ransomeye_ro")]
+    NotReadOnly { role: String, reason: String },
+
+    #[error("Query failed: {0}")]
+    Query(String),
+
+    #[error("Output error: {0}")]
+    Output(String),
+
+    #[error("Evidence error: {0}")]
+    Evidence(String),
+
+    #[error("Signing error: {0}")]
+    Signing(String),
+
+    #[error("Verification failed: {0}")]
+    Verification(String),
+}
+
+impl From<tokio_postgres::Error> for QueryError {
+    fn from(e: tokio_postgres::Error) -> Self {
+        QueryError::Query(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for QueryError {
+    fn from(e: std::io::Error) -> Self {
+        QueryError::Output(e.to_string())
+    }
+}
@@ -0,0 +1,127 @@
+// Path and File Name : /home/ransomeye/rebuild/ops/query/src/evidence.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Incident evidence from the reporting evidence store - sealed bundles linked to an incident or its detections, integrity-checked, with their packet captures, memory images and sandbox reports read back against the sealed hashes
+
+/*
+ * Incident Evidence
+ *
+ * A sealed bundle belongs to the export when one of its items names the incident
+ * (metadata incident_id) or one of the incident's detections (metadata detection_id). Every
+ * such bundle is checked against its sealed hash first; one that fails stops the export.
+ *
+ *   evidence/bundles/<bundle_id>.json     the sealed bundle
+ *   evidence/pcap/<capture_id>.pcap       read_pcap, verified against pcap_sha256
+ *   evidence/memory/<acquisition_id>.raw  export_image, every chunk and the image verified
+ *   evidence/sandbox/<submission_id>.report
+ *
+ * Memory images are written to a staging directory next to the archive instead of memory.
+ */
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use reporting::{memory_import, pcap_import, sandbox_import, EvidenceStore, EvidenceStoreOptions};
+use serde_json::{json, Value as JsonValue};
+
+use crate::archive::{ArchiveFile, FileSource};
+use crate::errors::QueryError;
+use crate::incident::TimelineEntry;
+
+/// Sealed evidence of one incident, ready for the archive.
+#[derive(Debug, Default)]
+pub struct IncidentEvidence {
+    pub files: Vec<ArchiveFile>,
+    /// One `evidence` timeline entry per sealed item
+    pub timeline: Vec<TimelineEntry>,
+    /// Custody trails sealed with the evidence (memory acquisitions)
+    pub custody: Vec<JsonValue>,
+    /// Ids (capture, acquisition, submission) of the artifacts included
+    pub artifact_ids: HashSet<String>,
+    pub bundles: usize,
+}
+
+fn evidence_error(e: impl std::fmt::Display) -> QueryError {
+    QueryError::Evidence(e.to_string())
+}
+
+/// Bundles of the store linked to `incident_id` or one of `detection_ids` (lowercase UUIDs).
+pub fn collect(
+    store_path: &Path,
+    staging: &Path,
+    incident_id: &str,
+    detection_ids: &HashSet<String>,
+) -> Result<IncidentEvidence, QueryError> {
+    if !store_path.is_dir() {
+        return Err(QueryError::Evidence(format!("evidence store {} does not exist", store_path.display())));
+    }
+    let options = EvidenceStoreOptions::from_env().map_err(evidence_error)?;
+    let store = EvidenceStore::open(store_path, None, options).map_err(evidence_error)?;
+    let linked = |metadata: &std::collections::HashMap<String, String>| {
+        metadata.get("incident_id").is_some_and(|id| id.eq_ignore_ascii_case(incident_id))
+            || metadata.get("detection_id").is_some_and(|id| detection_ids.contains(&id.to_ascii_lowercase()))
+    };
+
+    let mut evidence = IncidentEvidence::default();
+    let mut bundles = store.get_all_bundles();
+    bundles.sort_by(|a, b| (a.created_at, &a.bundle_id).cmp(&(b.created_at, &b.bundle_id)));
+    for bundle in bundles.into_iter().filter(|b| b.is_sealed) {
+        if !bundle.evidence_items.iter().any(|item| linked(&item.metadata)) {
+            continue;
+        }
+        if !store.verify_bundle_integrity(&bundle).map_err(evidence_error)? {
+            return Err(QueryError::Evidence(format!("bundle {} does not match its sealed hash", bundle.bundle_id)));
+        }
+        evidence.files.push(ArchiveFile::json(
+            format!("evidence/bundles/{}.json", bundle.bundle_id),
+            &serde_json::to_value(&bundle).map_err(evidence_error)?,
+        )?);
+        evidence.bundles += 1;
+
+        for item in bundle.evidence_items.iter().filter(|item| linked(&item.metadata)) {
+            let id = |key: &str| item.metadata.get(key).map(|v| v.to_ascii_lowercase()).unwrap_or_default();
+            let artifact = match item.source_type.as_str() {
+                pcap_import::PCAP_SOURCE_TYPE => {
+                    let capture_id = id("capture_id");
+                    let pcap = pcap_import::read_pcap(item).map_err(evidence_error)?;
+                    Some((capture_id.clone(), ArchiveFile::bytes(format!("evidence/pcap/{}.pcap", capture_id), pcap)))
+                }
+                memory_import::MEMORY_SOURCE_TYPE => {
+                    let acquisition_id = id("acquisition_id");
+                    fs::create_dir_all(staging)?;
+                    let image = staging.join(format!("{}.raw", acquisition_id));
+                    memory_import::export_image(&store, item, &image).map_err(evidence_error)?;
+                    evidence.custody.push(json!({
+                        "acquisition_id": acquisition_id,
+                        "custody": item.data.get("custody").cloned().unwrap_or(JsonValue::Null),
+                    }));
+                    let file = ArchiveFile {
+                        path: format!("evidence/memory/{}.raw", acquisition_id),
+                        source: FileSource::Path(image),
+                    };
+                    Some((acquisition_id, file))
+                }
+                sandbox_import::SANDBOX_SOURCE_TYPE => {
+                    let submission_id = id("submission_id");
+                    let report = sandbox_import::read_report(item).map_err(evidence_error)?;
+                    let path = format!("evidence/sandbox/{}.report", submission_id);
+                    Some((submission_id, ArchiveFile::bytes(path, report)))
+                }
+                // Other evidence is exported in its bundle only
+                _ => None,
+            };
+            if let Some((artifact_id, file)) = artifact {
+                evidence.artifact_ids.insert(artifact_id);
+                evidence.files.push(file);
+            }
+            evidence.timeline.push(TimelineEntry {
+                time: bundle.sealed_at.unwrap_or(item.timestamp),
+                kind: "evidence",
+                host: item.metadata.get("host_ip").cloned(),
+                summary: format!("{} sealed in evidence bundle {}", item.source_type, bundle.bundle_id),
+                reference: item.evidence_id.clone(),
+            });
+        }
+    }
+    Ok(evidence)
+}
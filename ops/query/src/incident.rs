@@ -0,0 +1,324 @@
+// Path and File Name : /home/ransomeye/rebuild/ops/query/src/incident.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Incident records for export - detections of an incident, events of its hosts around them, the raw events behind those, chain-of-custody rows, and the merged timeline written to the export archive
+
+/*
+ * Incident Records
+ *
+ * There is no incidents table: an incident is the set of rows naming its UUID. The export reads,
+ * in one READ ONLY, REPEATABLE READ transaction (connection.rs):
+ *
+ *   detections   detection_results with artifacts.incident_id, or linked from a capture or
+ *                sandbox submission of the incident
+ *   events       normalized_events of the incident's agents from --context before the first
+ *                detection to --context after the last one, plus every detection's trigger
+ *   raw events   raw_events behind those events, payload as stored (JSON, or bytes in base64)
+ *   custody      pcap_captures, memory_acquisitions and sandbox_submissions of the incident and
+ *                legal holds placed on it: who asked, who approved, when, and the hashes
+ *
+ * Events and raw events stop at --max-events; the manifest says when they were cut. As for
+ * `ransomeye query`, the incident id only ever reaches the database as a bind parameter.
+ */
+
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, Duration, Utc};
+use serde_json::{json, Value as JsonValue};
+
+use crate::errors::QueryError;
+
+pub const DEFAULT_CONTEXT_MINUTES: i64 = 60;
+pub const MAX_CONTEXT_MINUTES: i64 = 7 * 24 * 60;
+pub const DEFAULT_MAX_EVENTS: i64 = 100_000;
+pub const MAX_MAX_EVENTS: i64 = 1_000_000;
+
+pub(crate) const DETECTIONS_SQL: &str = "
+SELECT d.detection_id, d.created_at, d.detection_engine, d.detection_name, d.detection_category, d.mitre_tactic,
+       d.mitre_technique, d.severity, d.confidence, d.score, d.reasoning, d.artifacts, d.normalized_event_id,
+       ne.raw_event_id, ne.source_agent_id AS agent_id, a.host_hostname AS host, d.suppression_id
+FROM detection_results d
+LEFT JOIN normalized_events ne ON ne.normalized_event_id = d.normalized_event_id
+LEFT JOIN agents a ON a.agent_id = ne.source_agent_id
+WHERE lower(d.artifacts->>'incident_id') = $1::text
+   OR d.detection_id IN (SELECT detection_id FROM pcap_captures WHERE incident_id = $1::text::uuid)
+   OR d.detection_id IN (SELECT detection_id FROM sandbox_submissions WHERE incident_id = $1::text::uuid)
+ORDER BY d.created_at, d.detection_id";
+
+pub(crate) const EVENTS_SQL: &str = "
+SELECT COALESCE(ne.observed_at, ne.normalized_at) AS time, ne.normalized_at, ne.normalized_event_id, ne.raw_event_id,
+       ne.source_agent_id AS agent_id, a.host_hostname AS host, ne.source_type, ne.event_kind, ne.event_subkind,
+       ne.severity, ne.attributes
+FROM normalized_events ne
+LEFT JOIN agents a ON a.agent_id = ne.source_agent_id
+WHERE (ne.source_agent_id = ANY($1::text[]::uuid[]) AND ne.normalized_at >= $2 AND ne.normalized_at < $3)
+   OR ne.normalized_event_id = ANY($4::text[]::uuid[])
+ORDER BY ne.normalized_at, ne.normalized_event_id
+LIMIT $5";
+
+pub(crate) const RAW_EVENTS_SQL: &str = "
+SELECT r.raw_event_id, r.source_type, r.source_agent_id, r.observed_at, r.received_at, r.event_name, r.transport_id,
+       r.trace_id, r.payload_json, encode(r.payload_bytes, 'base64') AS payload_base64,
+       encode(r.payload_sha256, 'hex') AS payload_sha256, r.payload_storage, r.schema_version, r.is_replay
+FROM raw_events r
+WHERE r.raw_event_id = ANY($1::text[]::uuid[])
+ORDER BY r.received_at, r.raw_event_id";
+
+pub(crate) const CAPTURES_SQL: &str = "
+SELECT 'pcap_capture' AS record, c.capture_id AS id, c.detection_id, c.status, host(c.host_ip) AS host_ip, c.bpf_filter,
+       c.probe_agent_id AS agent_id, c.requested_at, c.claimed_at, c.finished_at, encode(c.pcap_sha256, 'hex') AS sha256,
+       c.pcap_bytes AS bytes, c.failure_reason
+FROM pcap_captures c
+WHERE c.incident_id = $1::text::uuid
+ORDER BY c.requested_at, c.capture_id";
+
+pub(crate) const ACQUISITIONS_SQL: &str = "
+SELECT 'memory_acquisition' AS record, m.acquisition_id AS id, m.agent_id, a.host_hostname AS host, m.status, m.scope,
+       m.pid, m.reason, m.requested_by, m.approved_by, m.requested_at, m.claimed_at, m.finished_at,
+       encode(m.image_sha256, 'hex') AS sha256, m.bytes_received AS bytes, m.tool, m.failure_reason
+FROM memory_acquisitions m
+LEFT JOIN agents a ON a.agent_id = m.agent_id
+WHERE m.incident_id = $1::text::uuid
+ORDER BY m.requested_at, m.acquisition_id";
+
+pub(crate) const SUBMISSIONS_SQL: &str = "
+SELECT 'sandbox_submission' AS record, s.submission_id AS id, s.detection_id, s.agent_id, s.status, s.source,
+       s.submitted_by, s.reason, s.mode, encode(s.sha256, 'hex') AS file_sha256, s.file_path, s.queued_at,
+       s.submitted_at, s.finished_at, s.verdict, s.score, encode(s.report_sha256, 'hex') AS sha256, s.failure_reason
+FROM sandbox_submissions s
+WHERE s.incident_id = $1::text::uuid
+ORDER BY s.queued_at, s.submission_id";
+
+pub(crate) const HOLDS_SQL: &str = "
+SELECT 'legal_hold' AS record, h.hold_id AS id, h.reason, h.placed_by, h.placed_at, h.released_by, h.release_reason,
+       h.released_at
+FROM legal_holds h
+WHERE h.subject_type = 'incident' AND h.subject_id = $1::text::uuid
+ORDER BY h.placed_at, h.hold_id";
+
+/// What to export and how far around the detections to look.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncidentQuery {
+    /// Lowercase hyphenated UUID
+    pub incident_id: String,
+    /// Events of the incident's hosts this long before the first and after the last detection
+    pub context: Duration,
+    pub max_events: i64,
+}
+
+impl IncidentQuery {
+    pub fn new(incident_id: &str, context_minutes: i64, max_events: i64) -> Result<Self, QueryError> {
+        let incident_id = canonical_uuid(incident_id)
+            .ok_or_else(|| QueryError::InvalidArgument(format!("incident id '{}' is not a UUID", incident_id)))?;
+        if !(0..=MAX_CONTEXT_MINUTES).contains(&context_minutes) {
+            return Err(QueryError::InvalidArgument(format!(
+                "--context-minutes must be between 0 and {}",
+                MAX_CONTEXT_MINUTES
+            )));
+        }
+        if !(1..=MAX_MAX_EVENTS).contains(&max_events) {
+            return Err(QueryError::InvalidArgument(format!("--max-events must be between 1 and {}", MAX_MAX_EVENTS)));
+        }
+        Ok(Self { incident_id, context: Duration::minutes(context_minutes), max_events })
+    }
+}
+
+/// Lowercase hyphenated form of a UUID, or None.
+fn canonical_uuid(value: &str) -> Option<String> {
+    let value = value.trim().to_ascii_lowercase();
+    let groups: Vec<&str> = value.split('-').collect();
+    let lengths = [8, 4, 4, 4, 12];
+    let valid = groups.len() == lengths.len()
+        && groups
+            .iter()
+            .zip(lengths)
+            .all(|(g, n)| g.len() == n && g.chars().all(|c| c.is_ascii_hexdigit()));
+    valid.then_some(value)
+}
+
+/// Rows of one incident, as read from the database (row_to_json objects).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IncidentRecords {
+    pub detections: Vec<JsonValue>,
+    pub events: Vec<JsonValue>,
+    pub raw_events: Vec<JsonValue>,
+    /// Capture, acquisition, submission and legal hold rows, each tagged with `record`
+    pub custody: Vec<JsonValue>,
+    /// More events matched than --max-events
+    pub events_truncated: bool,
+}
+
+pub(crate) fn str_field<'a>(row: &'a JsonValue, key: &str) -> Option<&'a str> {
+    row.get(key).and_then(|v| v.as_str())
+}
+
+pub(crate) fn time_field(row: &JsonValue, key: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(str_field(row, key)?).ok().map(|t| t.with_timezone(&Utc))
+}
+
+impl IncidentRecords {
+    /// Agents named by the detections and custody rows.
+    pub fn agent_ids(&self) -> Vec<String> {
+        let agents: BTreeSet<String> = self
+            .detections
+            .iter()
+            .chain(&self.custody)
+            .filter_map(|row| str_field(row, "agent_id"))
+            .map(|id| id.to_string())
+            .collect();
+        agents.into_iter().collect()
+    }
+
+    /// [first detection - context, last detection + context], or None without detections.
+    pub fn event_window(&self, context: Duration) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        let times: Vec<DateTime<Utc>> = self.detections.iter().filter_map(|d| time_field(d, "created_at")).collect();
+        let first = times.iter().min()?;
+        let last = times.iter().max()?;
+        // Half-open window: the last detection's own second stays inside
+        Some((*first - context, *last + context + Duration::seconds(1)))
+    }
+
+    /// Normalized events that triggered a detection (always exported).
+    pub fn trigger_event_ids(&self) -> Vec<String> {
+        let ids: BTreeSet<String> = self
+            .detections
+            .iter()
+            .filter_map(|d| str_field(d, "normalized_event_id"))
+            .map(|id| id.to_string())
+            .collect();
+        ids.into_iter().collect()
+    }
+
+    /// Raw events behind the exported events and the detections' triggers.
+    pub fn raw_event_ids(&self) -> Vec<String> {
+        let ids: BTreeSet<String> = self
+            .events
+            .iter()
+            .chain(&self.detections)
+            .filter_map(|row| str_field(row, "raw_event_id"))
+            .map(|id| id.to_string())
+            .collect();
+        ids.into_iter().collect()
+    }
+}
+
+/// One line of timeline.jsonl.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimelineEntry {
+    pub time: DateTime<Utc>,
+    /// event | detection | custody | evidence
+    pub kind: &'static str,
+    pub host: Option<String>,
+    pub summary: String,
+    /// Id of the row or evidence item the entry comes from
+    pub reference: String,
+}
+
+impl TimelineEntry {
+    pub fn to_json(&self) -> JsonValue {
+        json!({
+            "time": self.time.to_rfc3339(),
+            "kind": self.kind,
+            "host": self.host,
+            "summary": self.summary,
+            "ref": self.reference,
+        })
+    }
+}
+
+fn kind_order(kind: &str) -> u8 {
+    match kind {
+        "event" => 0,
+        "detection" => 1,
+        "custody" => 2,
+        _ => 3,
+    }
+}
+
+/// Custody steps of one capture, acquisition, submission or hold row.
+fn custody_steps(row: &JsonValue) -> Vec<(&'static str, String)> {
+    let who = |key: &str| str_field(row, key).map(|v| format!(" by {}", v)).unwrap_or_default();
+    match str_field(row, "record") {
+        Some("pcap_capture") => vec![
+            ("requested_at", "packet capture requested".to_string()),
+            ("claimed_at", "packet capture started by probe".to_string()),
+            ("finished_at", format!("packet capture {}", str_field(row, "status").unwrap_or("finished"))),
+        ],
+        Some("memory_acquisition") => vec![
+            (
+                "requested_at",
+                format!("memory acquisition requested{}, approved{}", who("requested_by"), who("approved_by")),
+            ),
+            ("claimed_at", "memory acquisition started by agent".to_string()),
+            ("finished_at", format!("memory acquisition {}", str_field(row, "status").unwrap_or("finished"))),
+        ],
+        Some("sandbox_submission") => vec![
+            ("queued_at", format!("sandbox submission queued{}", who("submitted_by"))),
+            ("submitted_at", "sample submitted to sandbox".to_string()),
+            (
+                "finished_at",
+                match str_field(row, "verdict") {
+                    Some(verdict) => format!("sandbox verdict {}", verdict),
+                    None => format!("sandbox submission {}", str_field(row, "status").unwrap_or("finished")),
+                },
+            ),
+        ],
+        Some("legal_hold") => vec![
+            ("placed_at", format!("legal hold placed{}", who("placed_by"))),
+            ("released_at", format!("legal hold released{}", who("released_by"))),
+        ],
+        _ => Vec::new(),
+    }
+}
+
+/// Events, detections and custody steps in time order (events before detections at the same
+/// instant, then custody, then sealed evidence), followed by `evidence` entries merged in.
+pub fn build_timeline(records: &IncidentRecords, evidence: &[TimelineEntry]) -> Vec<TimelineEntry> {
+    let mut entries: Vec<TimelineEntry> = Vec::new();
+    for event in &records.events {
+        let Some(time) = time_field(event, "time") else { continue };
+        let kind = str_field(event, "event_kind").unwrap_or("event");
+        let summary = match str_field(event, "event_subkind") {
+            Some(subkind) => format!("{} / {}", kind, subkind),
+            None => kind.to_string(),
+        };
+        entries.push(TimelineEntry {
+            time,
+            kind: "event",
+            host: str_field(event, "host").map(str::to_string),
+            summary,
+            reference: str_field(event, "normalized_event_id").unwrap_or_default().to_string(),
+        });
+    }
+    for detection in &records.detections {
+        let Some(time) = time_field(detection, "created_at") else { continue };
+        entries.push(TimelineEntry {
+            time,
+            kind: "detection",
+            host: str_field(detection, "host").map(str::to_string),
+            summary: format!(
+                "{} ({})",
+                str_field(detection, "detection_name").unwrap_or("detection"),
+                str_field(detection, "severity").unwrap_or("unknown")
+            ),
+            reference: str_field(detection, "detection_id").unwrap_or_default().to_string(),
+        });
+    }
+    for row in &records.custody {
+        for (key, summary) in custody_steps(row) {
+            if let Some(time) = time_field(row, key) {
+                entries.push(TimelineEntry {
+                    time,
+                    kind: "custody",
+                    host: str_field(row, "host").map(str::to_string),
+                    summary,
+                    reference: str_field(row, "id").unwrap_or_default().to_string(),
+                });
+            }
+        }
+    }
+    entries.extend(evidence.iter().cloned());
+    entries.sort_by(|a, b| {
+        (a.time, kind_order(a.kind), &a.reference).cmp(&(b.time, kind_order(b.kind), &b.reference))
+    });
+    entries
+}
@@ -0,0 +1,311 @@
+// Path and File Name : /home/ransomeye/rebuild/ops/query/src/investigation.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Canned investigative queries - events for a host, process tree around a pid, flows to an IP or CIDR, detections in a time range - with argument validation and fixed parameterized SQL
+
+/*
+ * Investigations
+ *
+ * Each investigation is one fixed SQL statement; operator input only ever reaches the database
+ * as bind parameters. A host is matched on agents.host_hostname, agents.host_fqdn or the agent
+ * id. Time windows are [since, until): on normalized_at for events, observed_at for process and
+ * flow telemetry and created_at for detections.
+ */
+
+use std::net::IpAddr;
+
+use chrono::{DateTime, Duration, Utc};
+use tokio_postgres::types::ToSql;
+
+use crate::errors::QueryError;
+
+pub const DEFAULT_LIMIT: i64 = 1000;
+pub const MAX_LIMIT: i64 = 100_000;
+/// Default window when --since is not given
+pub const DEFAULT_LOOKBACK_HOURS: i64 = 24;
+/// Severity names in severity_level order
+pub const SEVERITIES: [&str; 6] = ["debug", "info", "notice", "warning", "error", "critical"];
+
+/// Parse an absolute (RFC 3339) or relative time: `now`, or a number with unit s, m, h or d
+/// meaning that long before `now` (`90m`, `7d`).
+pub fn parse_time(value: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, QueryError> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("now") {
+        return Ok(now);
+    }
+    if let Ok(t) = DateTime::parse_from_rfc3339(value) {
+        return Ok(t.with_timezone(&Utc));
+    }
+    let invalid = || QueryError::InvalidArgument(format!("time '{}' is neither RFC 3339 nor relative (30m, 24h, 7d)", value));
+    let unit = value.chars().last().ok_or_else(invalid)?;
+    let amount: i64 = value[..value.len() - unit.len_utf8()].parse().map_err(|_| invalid())?;
+    if amount < 0 {
+        return Err(invalid());
+    }
+    let ago = match unit {
+        's' => Duration::try_seconds(amount),
+        'm' => Duration::try_minutes(amount),
+        'h' => Duration::try_hours(amount),
+        'd' => Duration::try_days(amount),
+        _ => None,
+    }
+    .ok_or_else(invalid)?;
+    now.checked_sub_signed(ago).ok_or_else(invalid)
+}
+
+/// Half-open query window [since, until).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeWindow {
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+}
+
+impl TimeWindow {
+    /// Window from optional --since / --until arguments (default: the last 24 hours).
+    pub fn parse(since: Option<&str>, until: Option<&str>, now: DateTime<Utc>) -> Result<Self, QueryError> {
+        let since = match since {
+            Some(s) => parse_time(s, now)?,
+            None => now - Duration::hours(DEFAULT_LOOKBACK_HOURS),
+        };
+        let until = match until {
+            Some(u) => parse_time(u, now)?,
+            None => now,
+        };
+        if since >= until {
+            return Err(QueryError::InvalidArgument(format!(
+                "--since ({}) must be before --until ({})",
+                since.to_rfc3339(),
+                until.to_rfc3339()
+            )));
+        }
+        Ok(Self { since, until })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Investigation {
+    /// Normalized events of one host, newest first
+    Events { host: String, window: TimeWindow, limit: i64 },
+    /// Ancestors and descendants of a pid on one host, per boot
+    ProcessTree { host: String, pid: i32, window: TimeWindow },
+    /// DPI flows and agent connections to an IP or CIDR, newest first
+    Flows { ip: String, port: Option<i32>, window: TimeWindow, limit: i64 },
+    /// Detections, newest first; suppressed ones only on request
+    Detections {
+        window: TimeWindow,
+        host: Option<String>,
+        min_severity: Option<String>,
+        include_suppressed: bool,
+        limit: i64,
+    },
+}
+
+const EVENTS_SQL: &str = "
+SELECT COALESCE(ne.observed_at, ne.normalized_at) AS time, a.host_hostname AS host, ne.event_kind, ne.event_subkind,
+       ne.severity, ne.source_type, ne.normalized_event_id
+FROM normalized_events ne
+JOIN agents a ON a.agent_id = ne.source_agent_id
+WHERE (a.host_hostname = $1 OR a.host_fqdn = $1 OR a.agent_id::text = $1)
+  AND ne.normalized_at >= $2 AND ne.normalized_at < $3
+ORDER BY ne.normalized_at DESC, ne.normalized_event_id DESC
+LIMIT $4";
+
+// First observation of every (boot, pid) in the window, then the parent chain (negative depth)
+// and the subtree (path = pids from the root) of the requested pid, guarded against pid cycles.
+const PROCESS_TREE_SQL: &str = "
+WITH RECURSIVE procs AS (
+  SELECT DISTINCT ON (t.boot_id, t.pid) t.boot_id, t.pid, t.ppid, t.process_name, t.process_path, t.cmdline, t.username, t.observed_at
+  FROM linux_agent_telemetry t
+  JOIN agents a ON a.agent_id = t.agent_id
+  WHERE (a.host_hostname = $1 OR a.host_fqdn = $1 OR a.agent_id::text = $1)
+    AND t.pid IS NOT NULL AND t.observed_at >= $3 AND t.observed_at < $4
+  ORDER BY t.boot_id, t.pid, t.observed_at
+),
+up AS (
+  SELECT p.boot_id, p.pid, p.ppid, 0 AS depth, ARRAY[p.pid] AS seen FROM procs p WHERE p.pid = $2
+  UNION ALL
+  SELECT p.boot_id, p.pid, p.ppid, up.depth - 1, up.seen || p.pid
+  FROM procs p JOIN up ON p.pid = up.ppid AND p.boot_id IS NOT DISTINCT FROM up.boot_id
+  WHERE NOT p.pid = ANY(up.seen) AND up.depth > -64
+),
+down AS (
+  SELECT p.boot_id, p.pid, 0 AS depth, ARRAY[p.pid] AS path FROM procs p WHERE p.pid = $2
+  UNION ALL
+  SELECT p.boot_id, p.pid, down.depth + 1, down.path || p.pid
+  FROM procs p JOIN down ON p.ppid = down.pid AND p.boot_id IS NOT DISTINCT FROM down.boot_id
+  WHERE NOT p.pid = ANY(down.path) AND down.depth < 64
+),
+tree AS (
+  SELECT boot_id, pid, depth, ARRAY[]::integer[] AS path FROM up WHERE depth < 0
+  UNION ALL
+  SELECT boot_id, pid, depth, path FROM down
+)
+SELECT tree.boot_id,
+       repeat('  ', tree.depth - MIN(tree.depth) OVER (PARTITION BY tree.boot_id)) || COALESCE(p.process_name, '?') AS process,
+       p.pid, p.ppid, p.username, p.observed_at AS first_seen, p.process_path, p.cmdline
+FROM tree
+JOIN procs p ON p.boot_id IS NOT DISTINCT FROM tree.boot_id AND p.pid = tree.pid
+ORDER BY tree.boot_id, tree.depth >= 0, CASE WHEN tree.depth < 0 THEN tree.depth END, tree.path";
+
+const FLOWS_SQL: &str = "
+SELECT * FROM (
+  SELECT d.observed_at AS time, 'dpi_probe' AS source, a.host_hostname AS host, host(d.src_ip) AS src_ip, d.src_port,
+         host(d.dst_ip) AS dst_ip, d.dst_port, d.protocol, d.byte_count AS bytes, NULL::text AS process,
+         COALESCE(d.tls_sni, d.http_host) AS server_name
+  FROM dpi_probe_telemetry d
+  JOIN agents a ON a.agent_id = d.agent_id
+  WHERE d.dst_ip <<= $1::text::inet AND ($2::int4 IS NULL OR d.dst_port = $2)
+    AND d.observed_at >= $3 AND d.observed_at < $4
+  UNION ALL
+  SELECT t.observed_at, 'linux_agent', a.host_hostname, host(t.network_src_ip), t.network_src_port,
+         host(t.network_dst_ip), t.network_dst_port, t.protocol, NULL::bigint, t.process_name, NULL::text
+  FROM linux_agent_telemetry t
+  JOIN agents a ON a.agent_id = t.agent_id
+  WHERE t.network_dst_ip <<= $1::text::inet AND ($2::int4 IS NULL OR t.network_dst_port = $2)
+    AND t.observed_at >= $3 AND t.observed_at < $4
+) f
+ORDER BY f.time DESC
+LIMIT $5";
+
+const DETECTIONS_SQL: &str = "
+SELECT d.created_at AS time, d.severity, d.detection_name, d.detection_category AS category, d.mitre_technique,
+       d.confidence, a.host_hostname AS host, d.detection_engine AS engine, d.suppression_id IS NOT NULL AS suppressed,
+       d.detection_id
+FROM detection_results d
+LEFT JOIN normalized_events ne ON ne.normalized_event_id = d.normalized_event_id
+LEFT JOIN agents a ON a.agent_id = ne.source_agent_id
+WHERE d.created_at >= $1 AND d.created_at < $2
+  AND ($3::text IS NULL OR a.host_hostname = $3 OR a.host_fqdn = $3 OR a.agent_id::text = $3)
+  AND ($4::text IS NULL OR d.severity >= $4::text::severity_level)
+  AND ($5::bool OR d.suppression_id IS NULL)
+ORDER BY d.created_at DESC, d.detection_id DESC
+LIMIT $6";
+
+fn check_limit(limit: i64) -> Result<(), QueryError> {
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(QueryError::InvalidArgument(format!("--limit must be between 1 and {}", MAX_LIMIT)));
+    }
+    Ok(())
+}
+
+fn check_host(host: &str) -> Result<(), QueryError> {
+    if host.trim().is_empty() {
+        return Err(QueryError::InvalidArgument("--host must not be empty".to_string()));
+    }
+    Ok(())
+}
+
+/// An address or CIDR block (`10.0.0.5`, `10.0.0.0/24`, `2001:db8::/32`).
+fn check_ip(ip: &str) -> Result<(), QueryError> {
+    let invalid = || QueryError::InvalidArgument(format!("'{}' is not an IP address or CIDR block", ip));
+    let (addr, prefix) = match ip.split_once('/') {
+        Some((addr, prefix)) => (addr, Some(prefix.parse::<u8>().map_err(|_| invalid())?)),
+        None => (ip, None),
+    };
+    let addr: IpAddr = addr.parse().map_err(|_| invalid())?;
+    let max_prefix = if addr.is_ipv4() { 32 } else { 128 };
+    if prefix.is_some_and(|p| p > max_prefix) {
+        return Err(invalid());
+    }
+    Ok(())
+}
+
+impl Investigation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Investigation::Events { .. } => "events",
+            Investigation::ProcessTree { .. } => "process-tree",
+            Investigation::Flows { .. } => "flows",
+            Investigation::Detections { .. } => "detections",
+        }
+    }
+
+    /// Check arguments before anything is sent to the database.
+    pub fn validate(&self) -> Result<(), QueryError> {
+        match self {
+            Investigation::Events { host, limit, .. } => {
+                check_host(host)?;
+                check_limit(*limit)
+            }
+            Investigation::ProcessTree { host, pid, .. } => {
+                check_host(host)?;
+                if *pid <= 0 {
+                    return Err(QueryError::InvalidArgument("--pid must be positive".to_string()));
+                }
+                Ok(())
+            }
+            Investigation::Flows { ip, port, limit, .. } => {
+                check_ip(ip)?;
+                if port.is_some_and(|p| !(1..=65535).contains(&p)) {
+                    return Err(QueryError::InvalidArgument("--port must be between 1 and 65535".to_string()));
+                }
+                check_limit(*limit)
+            }
+            Investigation::Detections { host, min_severity, limit, .. } => {
+                if let Some(host) = host {
+                    check_host(host)?;
+                }
+                if let Some(severity) = min_severity {
+                    if !SEVERITIES.contains(&severity.as_str()) {
+                        return Err(QueryError::InvalidArgument(format!(
+                            "--min-severity must be one of {}",
+                            SEVERITIES.join(", ")
+                        )));
+                    }
+                }
+                check_limit(*limit)
+            }
+        }
+    }
+
+    /// Result columns, in output order.
+    pub fn columns(&self) -> &'static [&'static str] {
+        match self {
+            Investigation::Events { .. } => {
+                &["time", "host", "event_kind", "event_subkind", "severity", "source_type", "normalized_event_id"]
+            }
+            Investigation::ProcessTree { .. } => {
+                &["boot_id", "process", "pid", "ppid", "username", "first_seen", "process_path", "cmdline"]
+            }
+            Investigation::Flows { .. } => &[
+                "time", "source", "host", "src_ip", "src_port", "dst_ip", "dst_port", "protocol", "bytes", "process",
+                "server_name",
+            ],
+            Investigation::Detections { .. } => &[
+                "time", "severity", "detection_name", "category", "mitre_technique", "confidence", "host", "engine",
+                "suppressed", "detection_id",
+            ],
+        }
+    }
+
+    pub fn sql(&self) -> &'static str {
+        match self {
+            Investigation::Events { .. } => EVENTS_SQL,
+            Investigation::ProcessTree { .. } => PROCESS_TREE_SQL,
+            Investigation::Flows { .. } => FLOWS_SQL,
+            Investigation::Detections { .. } => DETECTIONS_SQL,
+        }
+    }
+
+    /// Bind parameters of `sql()`, in placeholder order.
+    pub(crate) fn params(&self) -> Vec<Box<dyn ToSql + Sync + Send>> {
+        match self.clone() {
+            Investigation::Events { host, window, limit } => {
+                vec![Box::new(host), Box::new(window.since), Box::new(window.until), Box::new(limit)]
+            }
+            Investigation::ProcessTree { host, pid, window } => {
+                vec![Box::new(host), Box::new(pid), Box::new(window.since), Box::new(window.until)]
+            }
+            Investigation::Flows { ip, port, window, limit } => {
+                vec![Box::new(ip), Box::new(port), Box::new(window.since), Box::new(window.until), Box::new(limit)]
+            }
+            Investigation::Detections { window, host, min_severity, include_suppressed, limit } => vec![
+                Box::new(window.since),
+                Box::new(window.until),
+                Box::new(host),
+                Box::new(min_severity),
+                Box::new(include_suppressed),
+                Box::new(limit),
+            ],
+        }
+    }
+}
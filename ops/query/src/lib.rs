@@ -0,0 +1,18 @@
+// Path and File Name : /home/ransomeye/rebuild/ops/query/src/lib.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Operator query tool - canned investigative queries (host events, process tree, flows to an IP, detections) over a read-only database role, rendered as table, JSON or CSV, and signed incident export archives for external IR teams
+
+pub mod archive;
+pub mod connection;
+pub mod errors;
+pub mod evidence;
+pub mod incident;
+pub mod investigation;
+pub mod output;
+
+pub use archive::{ExportInfo, ExportSigner, ExportSummary, VerifiedArchive};
+pub use connection::{QueryConfig, ReadOnlySession};
+pub use errors::QueryError;
+pub use incident::{IncidentQuery, IncidentRecords};
+pub use investigation::{parse_time, Investigation, TimeWindow};
+pub use output::{OutputFormat, ResultSet};
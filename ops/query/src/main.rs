@@ -0,0 +1,272 @@
+// Path and File Name : /home/ransomeye/rebuild/ops/query/src/main.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: ransomeye CLI - `ransomeye query` runs canned investigations for operators without SQL access; `ransomeye incident export` writes a signed incident archive for external IR teams and `ransomeye incident verify` checks one
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::process;
+
+use chrono::Utc;
+use clap::{Args, Parser, Subcommand};
+use query::incident::{DEFAULT_CONTEXT_MINUTES, DEFAULT_MAX_EVENTS};
+use query::investigation::DEFAULT_LIMIT;
+use query::{
+    archive, evidence, ExportInfo, ExportSigner, IncidentQuery, Investigation, OutputFormat, QueryConfig, QueryError,
+    ReadOnlySession, TimeWindow,
+};
+
+#[derive(Parser)]
+#[command(name = "ransomeye")]
+#[command(about = "RansomEye operator tools")]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Run an investigative query as a read-only role (RANSOMEYE_QUERY_DB_USER)
+    Query {
+        /// Output format: table, json or csv
+        #[arg(long, global = true, default_value = "table")]
+        format: OutputFormat,
+        #[command(subcommand)]
+        query: QueryCommand,
+    },
+    /// Export an incident for external IR teams, or verify an export
+    Incident {
+        #[command(subcommand)]
+        command: IncidentCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum IncidentCommand {
+    /// Write a signed archive of an incident: timeline, detections, raw events, evidence and custody
+    Export {
+        /// Incident UUID
+        incident_id: String,
+        /// Archive to create (never overwritten)
+        #[arg(long)]
+        out: PathBuf,
+        /// Ed25519 export signing key (PKCS#8, PEM or DER)
+        #[arg(long)]
+        signing_key: PathBuf,
+        /// Evidence store with the incident's sealed captures, memory images and sandbox reports
+        #[arg(long)]
+        evidence_store: Option<PathBuf>,
+        /// Operator making the export (chain of custody)
+        #[arg(long)]
+        exported_by: String,
+        /// Case reference or request the export answers
+        #[arg(long)]
+        reason: String,
+        /// Receiving party
+        #[arg(long)]
+        recipient: Option<String>,
+        /// Events of the incident's hosts this long before the first and after the last detection
+        #[arg(long, default_value_t = DEFAULT_CONTEXT_MINUTES)]
+        context_minutes: i64,
+        /// Most events exported (raw events follow the events kept)
+        #[arg(long, default_value_t = DEFAULT_MAX_EVENTS)]
+        max_events: i64,
+    },
+    /// Check an export archive: manifest signature, key fingerprint and every file
+    Verify {
+        archive: PathBuf,
+        /// Fingerprint of the exporter's key, as given out of band
+        #[arg(long)]
+        public_key_sha256: Option<String>,
+    },
+}
+
+/// --since / --until: RFC 3339 or relative to now (30m, 24h, 7d)
+#[derive(Args)]
+struct WindowArgs {
+    /// Start of the window, inclusive (default: 24h)
+    #[arg(long)]
+    since: Option<String>,
+    /// End of the window, exclusive (default: now)
+    #[arg(long)]
+    until: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum QueryCommand {
+    /// Normalized events of a host, newest first
+    Events {
+        /// Hostname, FQDN or agent id
+        #[arg(long)]
+        host: String,
+        #[command(flatten)]
+        window: WindowArgs,
+        #[arg(long, default_value_t = DEFAULT_LIMIT)]
+        limit: i64,
+    },
+    /// Ancestors and descendants of a process on a Linux host
+    ProcessTree {
+        /// Hostname, FQDN or agent id
+        #[arg(long)]
+        host: String,
+        #[arg(long)]
+        pid: i32,
+        #[command(flatten)]
+        window: WindowArgs,
+    },
+    /// DPI flows and agent connections to an IP address or CIDR block
+    Flows {
+        #[arg(long)]
+        ip: String,
+        /// Destination port
+        #[arg(long)]
+        port: Option<i32>,
+        #[command(flatten)]
+        window: WindowArgs,
+        #[arg(long, default_value_t = DEFAULT_LIMIT)]
+        limit: i64,
+    },
+    /// Detections in a time range
+    Detections {
+        /// Only detections on this host (hostname, FQDN or agent id)
+        #[arg(long)]
+        host: Option<String>,
+        /// Lowest severity shown: debug, info, notice, warning, error, critical
+        #[arg(long)]
+        min_severity: Option<String>,
+        /// Include detections matched by a suppression rule
+        #[arg(long)]
+        include_suppressed: bool,
+        #[command(flatten)]
+        window: WindowArgs,
+        #[arg(long, default_value_t = DEFAULT_LIMIT)]
+        limit: i64,
+    },
+}
+
+fn investigation(query: QueryCommand) -> Result<Investigation, QueryError> {
+    let now = Utc::now();
+    let window = |w: &WindowArgs| TimeWindow::parse(w.since.as_deref(), w.until.as_deref(), now);
+    let investigation = match query {
+        QueryCommand::Events { host, window: w, limit } => Investigation::Events { host, window: window(&w)?, limit },
+        QueryCommand::ProcessTree { host, pid, window: w } => Investigation::ProcessTree { host, pid, window: window(&w)? },
+        QueryCommand::Flows { ip, port, window: w, limit } => Investigation::Flows { ip, port, window: window(&w)?, limit },
+        QueryCommand::Detections { host, min_severity, include_suppressed, window: w, limit } => Investigation::Detections {
+            window: window(&w)?,
+            host,
+            min_severity: min_severity.map(|s| s.to_ascii_lowercase()),
+            include_suppressed,
+            limit,
+        },
+    };
+    investigation.validate()?;
+    Ok(investigation)
+}
+
+async fn run_query(format: OutputFormat, query: QueryCommand) -> Result<(), QueryError> {
+    // Arguments are checked before connecting
+    let investigation = investigation(query)?;
+    let cfg = QueryConfig::from_env()?;
+    let mut session = ReadOnlySession::connect(&cfg).await?;
+    let results = session.run(&investigation).await?;
+    results.render(format, &mut std::io::stdout().lock())
+}
+
+async fn run_incident(command: IncidentCommand) -> Result<(), QueryError> {
+    match command {
+        IncidentCommand::Export {
+            incident_id,
+            out,
+            signing_key,
+            evidence_store,
+            exported_by,
+            reason,
+            recipient,
+            context_minutes,
+            max_events,
+        } => {
+            // Arguments and key are checked before connecting
+            let query = IncidentQuery::new(&incident_id, context_minutes, max_events)?;
+            let signer = ExportSigner::load(&signing_key)?;
+            if out.exists() {
+                return Err(QueryError::Output(format!("{} already exists", out.display())));
+            }
+            let cfg = QueryConfig::from_env()?;
+            let mut session = ReadOnlySession::connect(&cfg).await?;
+            let records = session.incident_records(&query).await?;
+            if records.detections.is_empty() && records.custody.is_empty() {
+                return Err(QueryError::InvalidArgument(format!("no detections or custody records for incident {}", query.incident_id)));
+            }
+            let info = ExportInfo {
+                incident_id: query.incident_id.clone(),
+                exported_at: Utc::now(),
+                exported_by,
+                reason,
+                recipient,
+                database_role: session.role().to_string(),
+            };
+            info.validate()?;
+
+            let staging = PathBuf::from(format!("{}.staging", out.display()));
+            let written = (|| {
+                let evidence = match &evidence_store {
+                    Some(store) => {
+                        let detection_ids: HashSet<String> = records
+                            .detections
+                            .iter()
+                            .filter_map(|d| d.get("detection_id").and_then(|v| v.as_str()))
+                            .map(|id| id.to_ascii_lowercase())
+                            .collect();
+                        Some(evidence::collect(store, &staging, &query.incident_id, &detection_ids)?)
+                    }
+                    None => None,
+                };
+                let export = archive::assemble(&records, evidence, &info)?;
+                let notes = export.notes.clone();
+                Ok::<_, QueryError>((archive::write_archive(&out, &info, export, &signer)?, notes))
+            })();
+            let _ = std::fs::remove_dir_all(&staging);
+            let (summary, notes) = written?;
+
+            println!("{}", summary.path.display());
+            println!("  files:            {} ({} bytes)", summary.files, summary.bytes);
+            println!("  archive sha256:   {}", summary.archive_sha256);
+            println!("  manifest sha256:  {}", summary.manifest_sha256);
+            println!("  key fingerprint:  {}  (give the recipient this fingerprint separately)", summary.public_key_sha256);
+            for note in notes {
+                println!("  note: {}", note);
+            }
+            Ok(())
+        }
+        IncidentCommand::Verify { archive: path, public_key_sha256 } => {
+            let verified = archive::verify_archive(&path, public_key_sha256.as_deref())?;
+            println!("OK: incident {} exported {} by {}", verified.incident_id, verified.exported_at, verified.exported_by);
+            println!("  {} file(s) match the signed manifest", verified.files);
+            if verified.key_pinned {
+                println!("  signed with the expected key {}", verified.public_key_sha256);
+            } else {
+                println!("  signed with key {} (not pinned: compare it with the exporter's fingerprint)", verified.public_key_sha256);
+            }
+            Ok(())
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+
+    match cli.command {
+        Commands::Query { format, query } => {
+            if let Err(e) = run_query(format, query).await {
+                eprintln!("ransomeye query: {}", e);
+                process::exit(1);
+            }
+        }
+        Commands::Incident { command } => {
+            if let Err(e) = run_incident(command).await {
+                eprintln!("ransomeye incident: {}", e);
+                process::exit(1);
+            }
+        }
+    }
+}
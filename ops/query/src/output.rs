@@ -0,0 +1,151 @@
+// Path and File Name : /home/ransomeye/rebuild/ops/query/src/output.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Query results and their output formats - aligned table for terminals, JSON array and CSV for other tools (columns always in query order)
+
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+
+use serde_json::Value as JsonValue;
+
+use crate::errors::QueryError;
+
+/// Longest cell printed in table output (JSON and CSV are never truncated)
+pub const MAX_TABLE_CELL_CHARS: usize = 80;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Table,
+    Json,
+    Csv,
+}
+
+impl OutputFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputFormat::Table => "table",
+            OutputFormat::Json => "json",
+            OutputFormat::Csv => "csv",
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "table" => Ok(OutputFormat::Table),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            other => Err(format!("unknown output format '{}' (table, json, csv)", other)),
+        }
+    }
+}
+
+/// Rows of one investigation; every row has one value per column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultSet {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<JsonValue>>,
+}
+
+impl ResultSet {
+    /// Pick `columns` out of row objects (missing keys become null).
+    pub fn from_objects(columns: &[&str], objects: &[JsonValue]) -> Self {
+        let rows = objects
+            .iter()
+            .map(|o| columns.iter().map(|c| o.get(*c).cloned().unwrap_or(JsonValue::Null)).collect())
+            .collect();
+        Self { columns: columns.iter().map(|c| c.to_string()).collect(), rows }
+    }
+
+    pub fn render(&self, format: OutputFormat, out: &mut impl Write) -> Result<(), QueryError> {
+        match format {
+            OutputFormat::Table => self.render_table(out),
+            OutputFormat::Json => self.render_json(out),
+            OutputFormat::Csv => self.render_csv(out),
+        }
+    }
+
+    fn render_table(&self, out: &mut impl Write) -> Result<(), QueryError> {
+        let cells: Vec<Vec<String>> = self.rows.iter().map(|r| r.iter().map(table_cell).collect()).collect();
+        let widths: Vec<usize> = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, c)| cells.iter().map(|r| r[i].chars().count()).chain([c.chars().count()]).max().unwrap_or(0))
+            .collect();
+
+        let line = |values: &[String]| -> String {
+            let padded: Vec<String> = values.iter().zip(&widths).map(|(v, w)| format!("{:<w$}", v, w = *w)).collect();
+            padded.join("  ").trim_end().to_string()
+        };
+        writeln!(out, "{}", line(&self.columns))?;
+        let rule: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+        writeln!(out, "{}", line(&rule))?;
+        for row in &cells {
+            writeln!(out, "{}", line(row))?;
+        }
+        writeln!(out, "({} row{})", self.rows.len(), if self.rows.len() == 1 { "" } else { "s" })?;
+        Ok(())
+    }
+
+    fn render_json(&self, out: &mut impl Write) -> Result<(), QueryError> {
+        // Written by hand so keys keep column order
+        if self.rows.is_empty() {
+            writeln!(out, "[]")?;
+            return Ok(());
+        }
+        writeln!(out, "[")?;
+        for (n, row) in self.rows.iter().enumerate() {
+            let fields: Vec<String> = self
+                .columns
+                .iter()
+                .zip(row)
+                .map(|(c, v)| format!("{}:{}", JsonValue::from(c.as_str()), v))
+                .collect();
+            let sep = if n + 1 < self.rows.len() { "," } else { "" };
+            writeln!(out, "  {{{}}}{}", fields.join(","), sep)?;
+        }
+        writeln!(out, "]")?;
+        Ok(())
+    }
+
+    fn render_csv(&self, out: &mut impl Write) -> Result<(), QueryError> {
+        let mut writer = csv::Writer::from_writer(out);
+        writer.write_record(&self.columns).map_err(|e| QueryError::Output(e.to_string()))?;
+        for row in &self.rows {
+            writer
+                .write_record(row.iter().map(plain_text))
+                .map_err(|e| QueryError::Output(e.to_string()))?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Strings without quotes, null as empty, everything else as JSON.
+fn plain_text(value: &JsonValue) -> String {
+    match value {
+        JsonValue::Null => String::new(),
+        JsonValue::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn table_cell(value: &JsonValue) -> String {
+    let text = plain_text(value).replace(['\n', '\r', '\t'], " ");
+    if text.chars().count() <= MAX_TABLE_CELL_CHARS {
+        return text;
+    }
+    let mut cut: String = text.chars().take(MAX_TABLE_CELL_CHARS - 1).collect();
+    cut.push('…');
+    cut
+}
@@ -0,0 +1,362 @@
+// Path and File Name : /home/ransomeye/rebuild/ops/query/tests/incident_export_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for incident export - argument checks, timeline order, archive layout and signature, detection of altered, added or re-signed files, and sealed evidence read from the evidence store
+
+use std::collections::HashSet;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use chrono::{TimeZone, Utc};
+use query::archive::{self, ArchiveFile, CHECKSUMS_FILE, MANIFEST_FILE, PUBLIC_KEY_FILE, SIGNATURE_FILE, VERIFY_FILE};
+use query::evidence;
+use query::incident::build_timeline;
+use query::{ExportInfo, ExportSigner, IncidentQuery, IncidentRecords, QueryError};
+use ring::rand::SystemRandom;
+use ring::signature::Ed25519KeyPair;
+use serde_json::{json, Value as JsonValue};
+use tempfile::TempDir;
+
+const INCIDENT: &str = "7c2f4a56-0a3e-4c1b-9a55-1f1f4c0e2b11";
+const DETECTION: &str = "0d9a1e2b-5c3f-4e8a-b7d6-2a4c6e8f0b13";
+const CAPTURE: &str = "3f1e2d4c-5b6a-4978-8a9b-0c1d2e3f4a5b";
+
+fn signer() -> ExportSigner {
+    let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+    ExportSigner::from_pkcs8(pkcs8.as_ref()).unwrap()
+}
+
+fn info() -> ExportInfo {
+    ExportInfo {
+        incident_id: INCIDENT.to_string(),
+        exported_at: Utc.with_ymd_and_hms(2026, 3, 15, 9, 0, 0).unwrap(),
+        exported_by: "alice".to_string(),
+        reason: "case 2026-031 handoff".to_string(),
+        recipient: Some("External IR".to_string()),
+        database_role: "alice".to_string(),
+    }
+}
+
+fn records() -> IncidentRecords {
+    IncidentRecords {
+        detections: vec![json!({
+            "detection_id": DETECTION,
+            "created_at": "2026-03-14T08:00:05+00:00",
+            "detection_name": "Mass file encryption",
+            "severity": "critical",
+            "host": "web-1",
+            "agent_id": "5b0c2f4e-9d1a-4c3b-8e7f-6a5d4c3b2a10",
+            "normalized_event_id": "11111111-1111-4111-8111-111111111111",
+            "raw_event_id": "22222222-2222-4222-8222-222222222222",
+        })],
+        events: vec![
+            json!({
+                "time": "2026-03-14T08:00:05+00:00",
+                "normalized_event_id": "11111111-1111-4111-8111-111111111111",
+                "raw_event_id": "22222222-2222-4222-8222-222222222222",
+                "host": "web-1",
+                "event_kind": "filesystem",
+                "event_subkind": "MassWrite",
+            }),
+            json!({
+                "time": "2026-03-14T07:59:00+00:00",
+                "normalized_event_id": "33333333-3333-4333-8333-333333333333",
+                "raw_event_id": "44444444-4444-4444-8444-444444444444",
+                "host": "web-1",
+                "event_kind": "process",
+                "event_subkind": "Exec",
+            }),
+        ],
+        raw_events: vec![json!({
+            "raw_event_id": "22222222-2222-4222-8222-222222222222",
+            "payload_json": {"event_type": "MassWrite"},
+            "payload_sha256": "ab".repeat(32),
+        })],
+        custody: vec![json!({
+            "record": "pcap_capture",
+            "id": CAPTURE,
+            "requested_at": "2026-03-14T08:00:10+00:00",
+            "claimed_at": "2026-03-14T08:00:20+00:00",
+            "finished_at": "2026-03-14T08:05:30+00:00",
+            "status": "uploaded",
+            "sha256": "cd".repeat(32),
+        })],
+        events_truncated: false,
+    }
+}
+
+fn export(dir: &Path, signer: &ExportSigner) -> std::path::PathBuf {
+    let out = dir.join("incident.tar");
+    let export = archive::assemble(&records(), None, &info()).unwrap();
+    archive::write_archive(&out, &info(), export, signer).unwrap();
+    out
+}
+
+/// Entries of a tar as (path, content).
+fn entries(path: &Path) -> Vec<(String, Vec<u8>)> {
+    let mut tar = tar::Archive::new(fs::File::open(path).unwrap());
+    tar.entries()
+        .unwrap()
+        .map(|e| {
+            let mut e = e.unwrap();
+            let name = e.path().unwrap().to_string_lossy().into_owned();
+            let mut data = Vec::new();
+            e.read_to_end(&mut data).unwrap();
+            (name, data)
+        })
+        .collect()
+}
+
+fn rewrite(path: &Path, entries: &[(String, Vec<u8>)]) {
+    let mut builder = tar::Builder::new(fs::File::create(path).unwrap());
+    for (name, data) in entries {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        builder.append_data(&mut header, name, data.as_slice()).unwrap();
+    }
+    builder.finish().unwrap();
+}
+
+fn file<'a>(entries: &'a [(String, Vec<u8>)], name: &str) -> &'a [u8] {
+    let path = format!("incident-{}/{}", INCIDENT, name);
+    &entries.iter().find(|(n, _)| *n == path).unwrap().1
+}
+
+#[test]
+fn test_incident_arguments() {
+    let query = IncidentQuery::new(&INCIDENT.to_uppercase(), 60, 1000).unwrap();
+    assert_eq!(query.incident_id, INCIDENT);
+    for bad in ["", "incident-42", "7c2f4a56-0a3e-4c1b-9a55-1f1f4c0e2b1", "7c2f4a56-0a3e-4c1b-9a55-1f1f4c0e2b1' OR '1'='1"] {
+        assert!(matches!(IncidentQuery::new(bad, 60, 1000), Err(QueryError::InvalidArgument(_))), "{}", bad);
+    }
+    assert!(IncidentQuery::new(INCIDENT, -1, 1000).is_err());
+    assert!(IncidentQuery::new(INCIDENT, 60, 0).is_err());
+
+    let records = records();
+    let (since, until) = records.event_window(chrono::Duration::minutes(30)).unwrap();
+    assert_eq!(since.to_rfc3339(), "2026-03-14T07:30:05+00:00");
+    assert_eq!(until.to_rfc3339(), "2026-03-14T08:30:06+00:00");
+    assert_eq!(records.agent_ids(), vec!["5b0c2f4e-9d1a-4c3b-8e7f-6a5d4c3b2a10"]);
+    assert_eq!(
+        records.raw_event_ids(),
+        vec!["22222222-2222-4222-8222-222222222222", "44444444-4444-4444-8444-444444444444"]
+    );
+    assert!(IncidentRecords::default().event_window(chrono::Duration::minutes(30)).is_none());
+
+    let mut missing_reason = info();
+    missing_reason.reason = " ".to_string();
+    assert!(matches!(missing_reason.validate(), Err(QueryError::InvalidArgument(_))));
+}
+
+#[test]
+fn test_timeline_in_time_order() {
+    let timeline = build_timeline(&records(), &[]);
+    let lines: Vec<(&str, &str)> = timeline.iter().map(|e| (e.kind, e.summary.as_str())).collect();
+    assert_eq!(
+        lines,
+        vec![
+            ("event", "process / Exec"),
+            ("event", "filesystem / MassWrite"),
+            ("detection", "Mass file encryption (critical)"),
+            ("custody", "packet capture requested"),
+            ("custody", "packet capture started by probe"),
+            ("custody", "packet capture uploaded"),
+        ]
+    );
+    assert_eq!(timeline[2].reference, DETECTION);
+    assert_eq!(timeline[2].to_json()["time"], "2026-03-14T08:00:05+00:00");
+}
+
+#[test]
+fn test_archive_layout_and_verification() {
+    let dir = TempDir::new().unwrap();
+    let signer = signer();
+    let out = export(dir.path(), &signer);
+
+    let entries = entries(&out);
+    let names: Vec<&str> = entries.iter().map(|(n, _)| n.strip_prefix(&format!("incident-{}/", INCIDENT)).unwrap()).collect();
+    assert_eq!(
+        names,
+        vec![
+            "detections.json",
+            "events.jsonl",
+            "raw_events.jsonl",
+            "timeline.jsonl",
+            "custody.json",
+            VERIFY_FILE,
+            CHECKSUMS_FILE,
+            MANIFEST_FILE,
+            SIGNATURE_FILE,
+            PUBLIC_KEY_FILE,
+        ]
+    );
+
+    let manifest: JsonValue = serde_json::from_slice(file(&entries, MANIFEST_FILE)).unwrap();
+    assert_eq!(manifest["incident_id"], INCIDENT);
+    assert_eq!(manifest["exported_by"], "alice");
+    assert_eq!(manifest["counts"]["detections"], 1);
+    assert_eq!(manifest["counts"]["timeline_entries"], 6);
+    assert_eq!(manifest["public_key_sha256"], signer.fingerprint());
+    assert_eq!(manifest["files"].as_array().unwrap().len(), 7);
+    assert!(manifest["notes"][0].as_str().unwrap().contains("no evidence store"));
+
+    // SHA256SUMS agrees with the manifest (sha256sum -c format)
+    let sums = String::from_utf8(file(&entries, CHECKSUMS_FILE).to_vec()).unwrap();
+    assert!(sums.contains(&format!("{}  detections.json\n", manifest["files"][0]["sha256"].as_str().unwrap())));
+    assert_eq!(sums.lines().count(), 6);
+
+    // The export itself is the last custody record
+    let custody: JsonValue = serde_json::from_slice(file(&entries, "custody.json")).unwrap();
+    assert_eq!(custody["export"]["reason"], "case 2026-031 handoff");
+    assert_eq!(custody["records"][0]["id"], CAPTURE);
+    let verify: JsonValue = serde_json::from_slice(file(&entries, VERIFY_FILE)).unwrap();
+    assert_eq!(verify["public_key_sha256"], signer.fingerprint());
+    assert!(String::from_utf8_lossy(file(&entries, PUBLIC_KEY_FILE)).starts_with("-----BEGIN PUBLIC KEY-----\n"));
+
+    let verified = archive::verify_archive(&out, Some(&signer.fingerprint().to_uppercase())).unwrap();
+    assert_eq!(verified.incident_id, INCIDENT);
+    assert_eq!(verified.files, 7);
+    assert!(verified.key_pinned);
+    assert!(!archive::verify_archive(&out, None).unwrap().key_pinned);
+
+    // Same records, key and time: same archive; an existing archive is never overwritten
+    let again = dir.path().join("again.tar");
+    archive::write_archive(&again, &info(), archive::assemble(&records(), None, &info()).unwrap(), &signer).unwrap();
+    assert_eq!(fs::read(&out).unwrap(), fs::read(&again).unwrap());
+    let refused = archive::write_archive(&out, &info(), archive::assemble(&records(), None, &info()).unwrap(), &signer);
+    assert!(matches!(refused, Err(QueryError::Output(_))));
+}
+
+#[test]
+fn test_altered_or_added_files_fail_verification() {
+    let dir = TempDir::new().unwrap();
+    let out = export(dir.path(), &signer());
+    let original = entries(&out);
+
+    let mut altered = original.clone();
+    altered[0].1[2] ^= 0x01;
+    rewrite(&out, &altered);
+    let err = archive::verify_archive(&out, None).unwrap_err().to_string();
+    assert!(err.contains("detections.json does not match"), "{}", err);
+
+    let mut added = original.clone();
+    added.insert(1, (format!("incident-{}/notes.txt", INCIDENT), b"unlisted".to_vec()));
+    rewrite(&out, &added);
+    assert!(archive::verify_archive(&out, None).unwrap_err().to_string().contains("notes.txt is not in the manifest"));
+
+    let mut missing = original.clone();
+    missing.remove(2);
+    rewrite(&out, &missing);
+    assert!(archive::verify_archive(&out, None).unwrap_err().to_string().contains("raw_events.jsonl missing"));
+
+    let mut manifest_edited = original.clone();
+    let index = manifest_edited.iter().position(|(n, _)| n.ends_with(MANIFEST_FILE)).unwrap();
+    manifest_edited[index].1 = String::from_utf8(manifest_edited[index].1.clone()).unwrap().replace("alice", "mallory").into_bytes();
+    rewrite(&out, &manifest_edited);
+    assert!(archive::verify_archive(&out, None).unwrap_err().to_string().contains("signature does not verify"));
+
+    let mut outside = original;
+    outside.push(("other-root/payload.sh".to_string(), Vec::new()));
+    rewrite(&out, &outside);
+    assert!(archive::verify_archive(&out, None).unwrap_err().to_string().contains("outside the archive root"));
+}
+
+#[test]
+fn test_resigned_archive_fails_pinned_key() {
+    let dir = TempDir::new().unwrap();
+    let exporter = signer();
+    let forger = signer();
+    // A complete archive under another key verifies on its own, never against the exporter's fingerprint
+    let out = dir.path().join("forged.tar");
+    archive::write_archive(&out, &info(), archive::assemble(&records(), None, &info()).unwrap(), &forger).unwrap();
+    assert!(archive::verify_archive(&out, None).is_ok());
+    let err = archive::verify_archive(&out, Some(&exporter.fingerprint())).unwrap_err().to_string();
+    assert!(err.contains(&format!("expected {}", exporter.fingerprint())), "{}", err);
+
+    // Reserved and escaping paths are refused at export
+    for path in [MANIFEST_FILE, "../x", "/etc/passwd", ""] {
+        let mut export = archive::assemble(&records(), None, &info()).unwrap();
+        export.files.push(ArchiveFile::bytes(path, Vec::new()));
+        let target = dir.path().join("refused.tar");
+        assert!(archive::write_archive(&target, &info(), export, &exporter).is_err(), "{}", path);
+        assert!(!target.exists());
+    }
+}
+
+fn spool_capture(spool: &Path, capture_id: &str, incident_id: &str, pcap: &[u8]) {
+    let sha256 = ring::digest::digest(&ring::digest::SHA256, pcap);
+    let manifest = json!({
+        "capture_id": capture_id,
+        "detection_id": DETECTION,
+        "incident_id": incident_id,
+        "host_ip": "10.1.2.3",
+        "bpf_filter": "host 10.1.2.3",
+        "probe_agent_id": "5b0c2f4e-9d1a-4c3b-8e7f-6a5d4c3b2a10",
+        "probe_component_id": "dpi-probe-01",
+        "requested_at": "2026-03-14T08:00:10Z",
+        "claimed_at": "2026-03-14T08:00:20Z",
+        "uploaded_at": "2026-03-14T08:05:30Z",
+        "pcap_file": format!("{}.pcap", capture_id),
+        "pcap_sha256": sha256.as_ref().iter().map(|b| format!("{:02x}", b)).collect::<String>(),
+        "pcap_bytes": pcap.len(),
+    });
+    fs::write(spool.join(format!("{}.pcap", capture_id)), pcap).unwrap();
+    fs::write(spool.join(format!("{}.json", capture_id)), manifest.to_string()).unwrap();
+}
+
+#[test]
+fn test_sealed_evidence_from_store() {
+    let dir = TempDir::new().unwrap();
+    let spool = dir.path().join("spool");
+    let store_path = dir.path().join("store");
+    fs::create_dir_all(&spool).unwrap();
+    let mut pcap = vec![0xd4, 0xc3, 0xb2, 0xa1, 2, 0, 4, 0];
+    pcap.resize(120, 0x5a);
+    spool_capture(&spool, CAPTURE, INCIDENT, &pcap);
+    // Another incident's capture, linked by neither incident nor detection
+    let other = "4a2b3c4d-5e6f-4a1b-9c2d-3e4f5a6b7c8d";
+    let mut manifest_pcap = pcap.clone();
+    manifest_pcap[40] = 1;
+    spool_capture(&spool, other, "9e8d7c6b-5a49-4838-9271-605f4e3d2c1b", &manifest_pcap);
+    let other_manifest = spool.join(format!("{}.json", other));
+    let unlinked = fs::read_to_string(&other_manifest).unwrap().replace(DETECTION, "aaaaaaaa-aaaa-4aaa-8aaa-aaaaaaaaaaaa");
+    fs::write(&other_manifest, unlinked).unwrap();
+    {
+        let store = reporting::EvidenceStore::new(&store_path, None).unwrap();
+        let collector = reporting::EvidenceCollector::new("1.0.0", "1.0.0");
+        let outcome = reporting::pcap_import::import_captures(&store, &collector, "1.0.0", "1.0.0", &spool).unwrap();
+        assert_eq!(outcome.imported.len(), 2);
+    }
+
+    let staging = dir.path().join("staging");
+    let detections = HashSet::from([DETECTION.to_string()]);
+    let found = evidence::collect(&store_path, &staging, INCIDENT, &detections).unwrap();
+    assert_eq!(found.bundles, 1);
+    assert_eq!(found.artifact_ids, HashSet::from([CAPTURE.to_string()]));
+    let paths: Vec<&str> = found.files.iter().map(|f| f.path.as_str()).collect();
+    assert!(paths[0].starts_with("evidence/bundles/"));
+    assert_eq!(paths[1], format!("evidence/pcap/{}.pcap", CAPTURE));
+    assert!(matches!(&found.files[1].source, archive::FileSource::Bytes(data) if *data == pcap));
+    assert_eq!(found.timeline[0].kind, "evidence");
+
+    // Sealed artifact present: no note for the capture, and the archive verifies
+    let out = dir.path().join("incident.tar");
+    let export = archive::assemble(&records(), Some(found), &info()).unwrap();
+    assert!(export.notes.is_empty(), "{:?}", export.notes);
+    archive::write_archive(&out, &info(), export, &signer()).unwrap();
+    let verified = archive::verify_archive(&out, None).unwrap();
+    assert_eq!(verified.manifest["counts"]["evidence_artifacts"], 1);
+    assert_eq!(verified.manifest["counts"]["timeline_entries"], 7);
+
+    // A bundle altered after sealing stops the export (the store refuses to load it)
+    let bundle_file = fs::read_dir(store_path.join("bundles"))
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .find(|p| fs::read_to_string(p).unwrap().contains(CAPTURE))
+        .unwrap();
+    let altered = fs::read_to_string(&bundle_file).unwrap().replace("\"engine_version\": \"1.0.0\"", "\"engine_version\": \"9.9.9\"");
+    fs::write(&bundle_file, altered).unwrap();
+    let err = evidence::collect(&store_path, &staging, INCIDENT, &detections).unwrap_err();
+    assert!(matches!(err, QueryError::Evidence(ref m) if m.contains("corrupt")), "{}", err);
+}
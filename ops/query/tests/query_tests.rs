@@ -0,0 +1,123 @@
+// Path and File Name : /home/ransomeye/rebuild/ops/query/tests/query_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for the operator query tool - time arguments, argument validation, bind parameters and table / JSON / CSV output
+
+use chrono::{Duration, TimeZone, Utc};
+use query::investigation::{MAX_LIMIT, SEVERITIES};
+use query::{parse_time, Investigation, OutputFormat, QueryError, ResultSet, TimeWindow};
+use serde_json::json;
+
+fn window() -> TimeWindow {
+    TimeWindow::parse(Some("24h"), None, Utc::now()).unwrap()
+}
+
+fn render(results: &ResultSet, format: OutputFormat) -> String {
+    let mut out = Vec::new();
+    results.render(format, &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn test_parse_time_absolute_and_relative() {
+    let now = Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap();
+    assert_eq!(parse_time("now", now).unwrap(), now);
+    assert_eq!(parse_time("90m", now).unwrap(), now - Duration::minutes(90));
+    assert_eq!(parse_time("7d", now).unwrap(), now - Duration::days(7));
+    assert_eq!(
+        parse_time("2026-02-01T08:00:00+02:00", now).unwrap(),
+        Utc.with_ymd_and_hms(2026, 2, 1, 6, 0, 0).unwrap()
+    );
+    for bad in ["", "yesterday", "5w", "-3h", "h"] {
+        assert!(matches!(parse_time(bad, now), Err(QueryError::InvalidArgument(_))), "{}", bad);
+    }
+}
+
+#[test]
+fn test_window_defaults_and_order() {
+    let now = Utc::now();
+    let w = TimeWindow::parse(None, None, now).unwrap();
+    assert_eq!(w.until, now);
+    assert_eq!(w.since, now - Duration::hours(24));
+    assert!(TimeWindow::parse(Some("1h"), Some("2h"), now).is_err());
+}
+
+#[test]
+fn test_validation_before_database() {
+    let ok = Investigation::Flows { ip: "10.0.0.0/24".into(), port: Some(445), window: window(), limit: 100 };
+    assert!(ok.validate().is_ok());
+    assert!(Investigation::Flows { ip: "2001:db8::/32".into(), port: None, window: window(), limit: 1 }.validate().is_ok());
+
+    let invalid = [
+        Investigation::Flows { ip: "10.0.0.0/33".into(), port: None, window: window(), limit: 1 },
+        Investigation::Flows { ip: "10.0.0.1; DROP TABLE agents".into(), port: None, window: window(), limit: 1 },
+        Investigation::Flows { ip: "10.0.0.1".into(), port: Some(70000), window: window(), limit: 1 },
+        Investigation::Events { host: " ".into(), window: window(), limit: 1 },
+        Investigation::Events { host: "web-1".into(), window: window(), limit: MAX_LIMIT + 1 },
+        Investigation::ProcessTree { host: "web-1".into(), pid: 0, window: window() },
+        Investigation::Detections {
+            window: window(),
+            host: None,
+            min_severity: Some("severe".into()),
+            include_suppressed: false,
+            limit: 10,
+        },
+    ];
+    for inv in invalid {
+        assert!(matches!(inv.validate(), Err(QueryError::InvalidArgument(_))), "{:?}", inv);
+    }
+}
+
+#[test]
+fn test_operator_input_only_in_bind_parameters() {
+    // Fixed statements: arguments never change the SQL text
+    let a = Investigation::Events { host: "web-1".into(), window: window(), limit: 10 };
+    let b = Investigation::Events { host: "x' OR '1'='1".into(), window: window(), limit: 10 };
+    assert_eq!(a.sql(), b.sql());
+    assert!(!b.sql().contains("x'"));
+    assert!(SEVERITIES.iter().all(|s| !a.sql().contains(s)));
+}
+
+#[test]
+fn test_table_output_aligned_and_truncated() {
+    let results = ResultSet::from_objects(
+        &["pid", "process", "cmdline"],
+        &[
+            json!({"pid": 1, "process": "systemd", "cmdline": null}),
+            json!({"pid": 4242, "process": "  bash", "cmdline": "x".repeat(200)}),
+        ],
+    );
+    let table = render(&results, OutputFormat::Table);
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(lines[0], format!("pid   process  {}", "cmdline"));
+    assert!(lines[1].starts_with("----  -------  -------"));
+    assert_eq!(lines[2], "1     systemd");
+    assert!(lines[3].starts_with("4242    bash   xxx"));
+    assert!(lines[3].ends_with('…'));
+    assert_eq!(lines[4], "(2 rows)");
+}
+
+#[test]
+fn test_json_keeps_column_order_and_types() {
+    let results = ResultSet::from_objects(
+        &["time", "confidence", "suppressed", "host"],
+        &[json!({"host": "web-1", "suppressed": false, "confidence": 0.9, "time": "2026-03-01T12:00:00+00:00"})],
+    );
+    let text = render(&results, OutputFormat::Json);
+    assert!(text.contains(r#"{"time":"2026-03-01T12:00:00+00:00","confidence":0.9,"suppressed":false,"host":"web-1"}"#));
+    let parsed: serde_json::Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(parsed[0]["confidence"], 0.9);
+
+    let empty = ResultSet::from_objects(&["time"], &[]);
+    assert_eq!(render(&empty, OutputFormat::Json), "[]\n");
+}
+
+#[test]
+fn test_csv_quotes_and_nulls() {
+    let results = ResultSet::from_objects(
+        &["process", "cmdline", "ppid"],
+        &[json!({"process": "sh", "cmdline": "sh -c \"echo a, b\"", "ppid": null})],
+    );
+    assert_eq!(render(&results, OutputFormat::Csv), "process,cmdline,ppid\nsh,\"sh -c \"\"echo a, b\"\"\",\n");
+    assert_eq!("CSV".parse::<OutputFormat>().unwrap(), OutputFormat::Csv);
+    assert!("xml".parse::<OutputFormat>().is_err());
+}
@@ -0,0 +1,289 @@
+// Path and File Name : /home/ransomeye/rebuild/ops/trust_init/src/ceremony.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Key ceremony - generates (or takes from an HSM) the root, policy, deception and audit keys, writes the trust store layout, a root-signed trust manifest and the fingerprint sheet
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use crypto::signature::{verify_ed25519, verify_rsa_pss_sha256};
+use serde::{Deserialize, Serialize};
+
+use crate::errors::TrustInitError;
+use crate::layout::TrustLayout;
+use crate::roles::{KeyRole, RoleKey, POLICY_KEY_BITS};
+use crate::sheet;
+
+/// Domain separation for proof-of-possession signatures in key requests
+const KEY_REQUEST_CONTEXT: &str = "ransomeye-trust-init:v1";
+
+#[derive(Debug, Clone)]
+pub struct CeremonyOptions {
+    pub layout: TrustLayout,
+    /// Deployment name printed on the fingerprint sheet and bound into the manifest
+    pub deployment: String,
+    /// Roles whose key already lives on a PKCS#11 token (`RANSOMEYE_<ROLE>_PKCS11_*`)
+    pub hsm_roles: Vec<KeyRole>,
+    pub policy_key_bits: usize,
+}
+
+impl CeremonyOptions {
+    pub fn new(layout: TrustLayout, deployment: impl Into<String>) -> Self {
+        Self { layout, deployment: deployment.into(), hsm_roles: Vec::new(), policy_key_bits: POLICY_KEY_BITS }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestKey {
+    pub role: KeyRole,
+    pub algorithm: String,
+    /// `generated` or `pkcs11`
+    pub source: String,
+    pub public_key_path: PathBuf,
+    /// Base64 of the public key file contents
+    pub public_key: String,
+    /// SHA-256 of the public key file contents, hex
+    pub fingerprint_sha256: String,
+}
+
+/// Every public key of the deployment, signed by the root key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrustManifest {
+    pub deployment: String,
+    pub created_at: DateTime<Utc>,
+    pub crypto_backend: String,
+    pub keys: Vec<ManifestKey>,
+}
+
+impl TrustManifest {
+    pub fn key(&self, role: KeyRole) -> Option<&ManifestKey> {
+        self.keys.iter().find(|k| k.role == role)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SignedManifest {
+    manifest: TrustManifest,
+    /// Ed25519 by the root key over the compact JSON of `manifest`, base64
+    root_signature: String,
+}
+
+/// Proof of possession for a token-held key: the token signs the deployment, role and
+/// fingerprint, so the key cannot be claimed for another role or deployment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyRequest {
+    pub deployment: String,
+    pub role: KeyRole,
+    pub algorithm: String,
+    pub token_label: String,
+    pub key_label: String,
+    pub public_key: String,
+    pub fingerprint_sha256: String,
+    pub created_at: DateTime<Utc>,
+    pub proof: String,
+}
+
+impl KeyRequest {
+    fn proof_message(deployment: &str, role: KeyRole, fingerprint: &str) -> Vec<u8> {
+        format!("{}:{}:{}:{}", KEY_REQUEST_CONTEXT, deployment, role, fingerprint).into_bytes()
+    }
+
+    /// Check the proof against the request's own public key
+    pub fn verify(&self) -> Result<(), TrustInitError> {
+        let public_key = general_purpose::STANDARD
+            .decode(&self.public_key)
+            .map_err(|e| TrustInitError::Serialization(format!("Invalid public key encoding: {}", e)))?;
+        let proof = general_purpose::STANDARD
+            .decode(&self.proof)
+            .map_err(|e| TrustInitError::Serialization(format!("Invalid proof encoding: {}", e)))?;
+        if sheet::fingerprint(&public_key) != self.fingerprint_sha256 {
+            return Err(TrustInitError::Serialization("Key request fingerprint does not match its public key".to_string()));
+        }
+        let message = Self::proof_message(&self.deployment, self.role, &self.fingerprint_sha256);
+        match self.role {
+            KeyRole::Policy => verify_rsa_pss_sha256(&public_key, &message, &proof)?,
+            _ => verify_ed25519(&public_key, &message, &proof)?,
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct CeremonyReport {
+    pub manifest: TrustManifest,
+    pub manifest_sha256: String,
+    pub layout: TrustLayout,
+}
+
+impl CeremonyReport {
+    /// Environment for the services consuming the new trust material
+    pub fn env_exports(&self) -> Vec<(String, String)> {
+        let layout = &self.layout;
+        vec![
+            ("RANSOMEYE_ROOT_KEY_PATH".to_string(), layout.root_public_key().display().to_string()),
+            ("RANSOMEYE_TRUST_STORE_PATH".to_string(), layout.policy_trust_dir().display().to_string()),
+            ("DECEPTION_PUBLIC_KEY_PATH".to_string(), layout.deception_public_key().display().to_string()),
+        ]
+    }
+}
+
+/// Run the ceremony. FAIL-CLOSED: nothing is written if any target already exists, and any
+/// failure aborts before the manifest and fingerprint sheet are produced.
+pub fn run(options: &CeremonyOptions) -> Result<CeremonyReport, TrustInitError> {
+    let layout = &options.layout;
+    let on_token = |role: KeyRole| options.hsm_roles.contains(&role);
+
+    let mut targets = vec![layout.manifest(), layout.fingerprint_sheet()];
+    for role in KeyRole::ALL {
+        targets.push(layout.public_key(role));
+        targets.push(if on_token(role) { layout.key_request(role) } else { layout.private_key(role) });
+    }
+    if let Some(existing) = targets.into_iter().find(|path| path.exists()) {
+        return Err(TrustInitError::AlreadyInitialized(existing));
+    }
+
+    // Keys first: a missing token or failed generation leaves nothing on disk
+    let mut keys = Vec::with_capacity(KeyRole::ALL.len());
+    for role in KeyRole::ALL {
+        let key = if on_token(role) { RoleKey::from_token(role)? } else { RoleKey::generate(role, options.policy_key_bits)? };
+        keys.push((role, key));
+    }
+
+    let created_at = Utc::now();
+    let mut manifest_keys = Vec::with_capacity(keys.len());
+    for (role, key) in &keys {
+        let public_file = public_key_file(*role, key);
+        let fingerprint = sheet::fingerprint(&public_file);
+        write_public(&layout.public_key(*role), &public_file)?;
+        match key.private_key() {
+            Some(private) => write_private(&layout.private_key(*role), &private)?,
+            None => {
+                let (token_label, key_label) = key.token().unwrap_or_default();
+                let request = KeyRequest {
+                    deployment: options.deployment.clone(),
+                    role: *role,
+                    algorithm: role.algorithm().to_string(),
+                    token_label: token_label.to_string(),
+                    key_label: key_label.to_string(),
+                    public_key: general_purpose::STANDARD.encode(key.public_key()),
+                    fingerprint_sha256: sheet::fingerprint(&key.public_key()),
+                    created_at,
+                    proof: String::new(),
+                };
+                let message = KeyRequest::proof_message(&request.deployment, *role, &request.fingerprint_sha256);
+                let request = KeyRequest { proof: general_purpose::STANDARD.encode(key.sign(&message)?), ..request };
+                write_public(&layout.key_request(*role), &to_json(&request)?)?;
+            }
+        }
+        manifest_keys.push(ManifestKey {
+            role: *role,
+            algorithm: role.algorithm().to_string(),
+            source: key.source().to_string(),
+            public_key_path: layout.public_key(*role),
+            public_key: general_purpose::STANDARD.encode(&public_file),
+            fingerprint_sha256: fingerprint,
+        });
+    }
+
+    let manifest = TrustManifest {
+        deployment: options.deployment.clone(),
+        created_at,
+        crypto_backend: crypto::backend().to_string(),
+        keys: manifest_keys,
+    };
+    let manifest_bytes = serde_json::to_vec(&manifest).map_err(|e| TrustInitError::Serialization(e.to_string()))?;
+    let root = &keys.iter().find(|(role, _)| *role == KeyRole::Root).expect("root key is always generated").1;
+    let signed = SignedManifest {
+        root_signature: general_purpose::STANDARD.encode(root.sign(&manifest_bytes)?),
+        manifest: manifest.clone(),
+    };
+    let signed_bytes = to_json(&signed)?;
+    write_public(&layout.manifest(), &signed_bytes)?;
+    let manifest_sha256 = sheet::fingerprint(&signed_bytes);
+
+    let report = CeremonyReport { manifest, manifest_sha256, layout: layout.clone() };
+    write_public(&layout.fingerprint_sheet(), sheet::render(&report).as_bytes())?;
+    Ok(report)
+}
+
+/// Load a trust manifest, check the root signature against the root public key installed at
+/// `root_public_key`, and check every installed public key against its listed fingerprint
+pub fn verify_manifest(manifest_path: &Path, root_public_key: &Path) -> Result<TrustManifest, TrustInitError> {
+    let signed: SignedManifest = serde_json::from_slice(&fs::read(manifest_path)?)
+        .map_err(|e| TrustInitError::Serialization(format!("Invalid trust manifest: {}", e)))?;
+    let installed = fs::read(root_public_key)?;
+    let listed = signed
+        .manifest
+        .key(KeyRole::Root)
+        .ok_or_else(|| TrustInitError::Serialization("Trust manifest has no root key".to_string()))?;
+    if listed.fingerprint_sha256 != sheet::fingerprint(&installed) {
+        return Err(TrustInitError::Serialization("Installed root key does not match the trust manifest".to_string()));
+    }
+    let signature = general_purpose::STANDARD
+        .decode(&signed.root_signature)
+        .map_err(|e| TrustInitError::Serialization(format!("Invalid manifest signature encoding: {}", e)))?;
+    let manifest_bytes = serde_json::to_vec(&signed.manifest).map_err(|e| TrustInitError::Serialization(e.to_string()))?;
+    verify_ed25519(&installed, &manifest_bytes, &signature)?;
+    for key in &signed.manifest.keys {
+        let contents = fs::read(&key.public_key_path)?;
+        if sheet::fingerprint(&contents) != key.fingerprint_sha256 {
+            return Err(TrustInitError::Serialization(format!(
+                "Installed {} key {} does not match the trust manifest",
+                key.role,
+                key.public_key_path.display()
+            )));
+        }
+    }
+    Ok(signed.manifest)
+}
+
+/// File contents a consumer reads for `role` (the audit verifier takes hex)
+fn public_key_file(role: KeyRole, key: &RoleKey) -> Vec<u8> {
+    match role {
+        KeyRole::Audit => hex::encode(key.public_key()).into_bytes(),
+        _ => key.public_key(),
+    }
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<Vec<u8>, TrustInitError> {
+    serde_json::to_vec_pretty(value).map_err(|e| TrustInitError::Serialization(e.to_string()))
+}
+
+fn write_public(path: &Path, contents: &[u8]) -> Result<(), TrustInitError> {
+    write_new(path, contents, 0o644, 0o755)
+}
+
+fn write_private(path: &Path, contents: &[u8]) -> Result<(), TrustInitError> {
+    write_new(path, contents, 0o600, 0o700)
+}
+
+fn write_new(path: &Path, contents: &[u8], mode: u32, dir_mode: u32) -> Result<(), TrustInitError> {
+    if let Some(parent) = path.parent() {
+        if !parent.exists() {
+            fs::create_dir_all(parent)?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(parent, fs::Permissions::from_mode(dir_mode))?;
+            }
+        }
+    }
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(mode);
+    }
+    #[cfg(not(unix))]
+    let _ = (mode, dir_mode);
+    let mut file = options.open(path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::AlreadyExists => TrustInitError::AlreadyInitialized(path.to_path_buf()),
+        _ => TrustInitError::Io(e),
+    })?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    Ok(())
+}
@@ -0,0 +1,24 @@
+// Path and File Name : /home/ransomeye/rebuild/ops/trust_init/src/errors.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Error types for trust initialization - every error aborts the ceremony (fail-closed)
+
+use std::path::PathBuf;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum TrustInitError {
+    #[error("Trust material already exists: {0} (refusing to overwrite)")]
+    AlreadyInitialized(PathBuf),
+
+    #[error("Crypto error: {0}")]
+    Crypto(#[from] crypto::CryptoError),
+
+    #[error("HSM error: {0}")]
+    Hsm(String),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Serialization error: {0}")]
+    Serialization(String),
+}
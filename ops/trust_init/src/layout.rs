@@ -0,0 +1,87 @@
+// Path and File Name : /home/ransomeye/rebuild/ops/trust_init/src/layout.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Trust store layout - where the Kernel, PolicyEngine, deception and audit consumers expect each key, and where ceremony-only material goes
+
+use std::path::{Path, PathBuf};
+
+use crate::roles::KeyRole;
+
+/// Key id the policy engine and sign_policies use for the v1.0 policy root
+pub const POLICY_ROOT_ID: &str = "policy_root_v1";
+
+/// Paths under a deployment root (`/etc/ransomeye` in production).
+///
+/// Public keys and the online audit key go where the consumers read them by default; the root,
+/// policy and deception private keys go to the ceremony directory, which is meant to be moved to
+/// offline media once the ceremony is over.
+#[derive(Debug, Clone)]
+pub struct TrustLayout {
+    pub root_dir: PathBuf,
+    pub ceremony_dir: PathBuf,
+}
+
+impl TrustLayout {
+    pub fn new(root_dir: impl AsRef<Path>, ceremony_dir: Option<&Path>) -> Self {
+        let root_dir = root_dir.as_ref().to_path_buf();
+        let ceremony_dir = ceremony_dir.map(Path::to_path_buf).unwrap_or_else(|| root_dir.join("ceremony"));
+        Self { root_dir, ceremony_dir }
+    }
+
+    /// Kernel: `RANSOMEYE_ROOT_KEY_PATH`
+    pub fn root_public_key(&self) -> PathBuf {
+        self.root_dir.join("keys/root.pub")
+    }
+
+    /// PolicyEngine: `RANSOMEYE_TRUST_STORE_PATH`
+    pub fn policy_trust_dir(&self) -> PathBuf {
+        self.root_dir.join("policy/trust")
+    }
+
+    /// Deception: `DECEPTION_PUBLIC_KEY_PATH` (raw 32-byte Ed25519 key despite the extension)
+    pub fn deception_public_key(&self) -> PathBuf {
+        self.root_dir.join("keys/deception_public_key.pem")
+    }
+
+    /// Reporting `--audit-key` (32-byte seed, online)
+    pub fn audit_signing_key(&self) -> PathBuf {
+        self.root_dir.join("keys/audit_signing.key")
+    }
+
+    /// Hex verifying key for `AuditVerifier`
+    pub fn audit_public_key(&self) -> PathBuf {
+        self.root_dir.join("keys/audit_public_key.hex")
+    }
+
+    /// Where consumers read the public half of `role`
+    pub fn public_key(&self, role: KeyRole) -> PathBuf {
+        match role {
+            KeyRole::Root => self.root_public_key(),
+            KeyRole::Policy => self.policy_trust_dir().join(format!("{}.der", POLICY_ROOT_ID)),
+            KeyRole::Deception => self.deception_public_key(),
+            KeyRole::Audit => self.audit_public_key(),
+        }
+    }
+
+    /// Private half of a locally generated key
+    pub fn private_key(&self, role: KeyRole) -> PathBuf {
+        match role {
+            KeyRole::Root => self.ceremony_dir.join("private/root.key"),
+            KeyRole::Policy => self.ceremony_dir.join(format!("private/{}.pk8.der", POLICY_ROOT_ID)),
+            KeyRole::Deception => self.ceremony_dir.join("private/deception_signing.key"),
+            KeyRole::Audit => self.audit_signing_key(),
+        }
+    }
+
+    /// Proof-of-possession request for a token-held key
+    pub fn key_request(&self, role: KeyRole) -> PathBuf {
+        self.ceremony_dir.join(format!("requests/{}.json", role.name()))
+    }
+
+    pub fn manifest(&self) -> PathBuf {
+        self.ceremony_dir.join("trust_manifest.json")
+    }
+
+    pub fn fingerprint_sheet(&self) -> PathBuf {
+        self.ceremony_dir.join("fingerprints.txt")
+    }
+}
@@ -0,0 +1,14 @@
+// Path and File Name : /home/ransomeye/rebuild/ops/trust_init/src/lib.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Trust initialization (key ceremony) for a new deployment - root, policy, deception and audit keys, trust store layout and fingerprint sheet
+
+pub mod ceremony;
+pub mod errors;
+pub mod layout;
+pub mod roles;
+pub mod sheet;
+
+pub use ceremony::{run, verify_manifest, CeremonyOptions, CeremonyReport, KeyRequest, TrustManifest};
+pub use errors::TrustInitError;
+pub use layout::TrustLayout;
+pub use roles::KeyRole;
@@ -0,0 +1,95 @@
+// Path and File Name : /home/ransomeye/rebuild/ops/trust_init/src/main.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: trust-init CLI - runs the key ceremony for a new deployment and verifies an existing trust manifest
+
+use std::path::PathBuf;
+use std::process;
+
+use clap::{Parser, Subcommand};
+use trust_init::{CeremonyOptions, KeyRole, TrustLayout};
+
+#[derive(Parser)]
+#[command(name = "trust-init")]
+#[command(about = "RansomEye key ceremony - establish the root of trust for a deployment")]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Generate the root, policy, deception and audit keys and write the trust store
+    Init {
+        /// Deployment root (trust store layout is created under it)
+        #[arg(long, default_value = "/etc/ransomeye")]
+        root_dir: PathBuf,
+        /// Offline material: private keys, key requests, manifest, fingerprint sheet (default <root-dir>/ceremony)
+        #[arg(long)]
+        ceremony_dir: Option<PathBuf>,
+        /// Deployment name bound into the manifest and printed on the fingerprint sheet
+        #[arg(long)]
+        deployment: String,
+        /// Roles whose key already lives on a PKCS#11 token (RANSOMEYE_<ROLE>_PKCS11_*), comma separated
+        #[arg(long, value_delimiter = ',')]
+        hsm: Vec<KeyRole>,
+    },
+    /// Verify a trust manifest against the installed root public key
+    Verify {
+        #[arg(long, default_value = "/etc/ransomeye")]
+        root_dir: PathBuf,
+        #[arg(long)]
+        ceremony_dir: Option<PathBuf>,
+    },
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    // FAIL-CLOSED: no keys from a crypto module that fails its self-check
+    if let Err(e) = crypto::self_check() {
+        eprintln!("Crypto self-check failed: {}", e);
+        process::exit(1);
+    }
+
+    match cli.command {
+        Commands::Init { root_dir, ceremony_dir, deployment, hsm } => {
+            let layout = TrustLayout::new(&root_dir, ceremony_dir.as_deref());
+            let mut options = CeremonyOptions::new(layout, deployment);
+            options.hsm_roles = hsm;
+            match trust_init::run(&options) {
+                Ok(report) => {
+                    for key in &report.manifest.keys {
+                        println!("✓ {:<10} {:<20} {}", key.role.name(), key.algorithm, key.public_key_path.display());
+                    }
+                    println!();
+                    println!("Fingerprint sheet: {}", report.layout.fingerprint_sheet().display());
+                    println!("Trust manifest:    {}", report.layout.manifest().display());
+                    println!();
+                    println!("Service environment:");
+                    for (name, value) in report.env_exports() {
+                        println!("  {}={}", name, value);
+                    }
+                    println!();
+                    println!("Print the fingerprint sheet, verify it out of band, then move");
+                    println!("{} to offline media.", report.layout.ceremony_dir.join("private").display());
+                }
+                Err(e) => {
+                    eprintln!("Trust initialization failed: {}", e);
+                    process::exit(1);
+                }
+            }
+        }
+        Commands::Verify { root_dir, ceremony_dir } => {
+            let layout = TrustLayout::new(&root_dir, ceremony_dir.as_deref());
+            match trust_init::verify_manifest(&layout.manifest(), &layout.root_public_key()) {
+                Ok(manifest) => {
+                    println!("✓ Trust manifest for '{}' verified ({} keys)", manifest.deployment, manifest.keys.len());
+                }
+                Err(e) => {
+                    eprintln!("Trust manifest verification failed: {}", e);
+                    process::exit(1);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,164 @@
+// Path and File Name : /home/ransomeye/rebuild/ops/trust_init/src/roles.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Deployment key roles (root, policy, deception, audit) and the key each one holds - generated locally or on a PKCS#11 token
+
+use std::fmt;
+use std::str::FromStr;
+
+use crypto::signature::{Ed25519KeyPair, RsaPssKeyPair};
+use serde::{Deserialize, Serialize};
+
+use crate::errors::TrustInitError;
+
+/// RSA size of the policy root (sign_policies and the policy engine require RSA-4096)
+pub const POLICY_KEY_BITS: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyRole {
+    Root,
+    Policy,
+    Deception,
+    Audit,
+}
+
+impl KeyRole {
+    pub const ALL: [KeyRole; 4] = [KeyRole::Root, KeyRole::Policy, KeyRole::Deception, KeyRole::Audit];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyRole::Root => "root",
+            KeyRole::Policy => "policy",
+            KeyRole::Deception => "deception",
+            KeyRole::Audit => "audit",
+        }
+    }
+
+    pub fn algorithm(self) -> &'static str {
+        match self {
+            KeyRole::Policy => "RSA-4096-PSS-SHA256",
+            _ => "Ed25519",
+        }
+    }
+
+    /// Env prefix of the token-held key (`RANSOMEYE_AUDIT_PKCS11_MODULE`, ...)
+    pub fn pkcs11_env_prefix(self) -> String {
+        format!("RANSOMEYE_{}_PKCS11", self.name().to_uppercase())
+    }
+}
+
+impl fmt::Display for KeyRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for KeyRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        KeyRole::ALL
+            .into_iter()
+            .find(|role| role.name() == s)
+            .ok_or_else(|| format!("unknown key role '{}' (expected root, policy, deception or audit)", s))
+    }
+}
+
+/// A role's key for the duration of the ceremony
+pub enum RoleKey {
+    Ed25519 { key_pair: Ed25519KeyPair, seed: [u8; 32] },
+    Rsa(RsaPssKeyPair),
+    #[cfg(feature = "pkcs11")]
+    Token(crypto::pkcs11::Pkcs11Key),
+}
+
+impl RoleKey {
+    /// Fresh key from the crypto backend
+    pub fn generate(role: KeyRole, policy_key_bits: usize) -> Result<Self, TrustInitError> {
+        match role {
+            KeyRole::Policy => Ok(RoleKey::Rsa(RsaPssKeyPair::generate(policy_key_bits)?)),
+            _ => {
+                let mut seed = [0u8; 32];
+                crypto::rand::fill(&mut seed)?;
+                Ok(RoleKey::Ed25519 { key_pair: Ed25519KeyPair::from_seed(&seed)?, seed })
+            }
+        }
+    }
+
+    /// Key already generated on the token `RANSOMEYE_<ROLE>_PKCS11_*` selects. FAIL-CLOSED: the
+    /// token must be present and the key must have the role's algorithm.
+    #[cfg(feature = "pkcs11")]
+    pub fn from_token(role: KeyRole) -> Result<Self, TrustInitError> {
+        use crypto::pkcs11::{Pkcs11Config, Pkcs11Key, Pkcs11KeyKind};
+
+        let prefix = role.pkcs11_env_prefix();
+        let config = Pkcs11Config::from_env(&prefix)?
+            .ok_or_else(|| TrustInitError::Hsm(format!("{} key requested on HSM but {}_MODULE is not set", role, prefix)))?;
+        let key = Pkcs11Key::open(&config)?;
+        let expected = match role {
+            KeyRole::Policy => Pkcs11KeyKind::RsaPssSha256,
+            _ => Pkcs11KeyKind::Ed25519,
+        };
+        if key.kind() != expected {
+            return Err(TrustInitError::Hsm(format!(
+                "{} key '{}' is {:?}, expected {:?}",
+                role, config.key_label, key.kind(), expected
+            )));
+        }
+        Ok(RoleKey::Token(key))
+    }
+
+    #[cfg(not(feature = "pkcs11"))]
+    pub fn from_token(role: KeyRole) -> Result<Self, TrustInitError> {
+        Err(TrustInitError::Hsm(format!(
+            "{} key requested on HSM but trust-init was built without the pkcs11 feature",
+            role
+        )))
+    }
+
+    /// Public key in the format its consumer reads (raw Ed25519, DER `RSAPublicKey`)
+    pub fn public_key(&self) -> Vec<u8> {
+        match self {
+            RoleKey::Ed25519 { key_pair, .. } => key_pair.public_key().to_vec(),
+            RoleKey::Rsa(key_pair) => key_pair.public_key(),
+            #[cfg(feature = "pkcs11")]
+            RoleKey::Token(key) => key.public_key().to_vec(),
+        }
+    }
+
+    /// Private key bytes to write out; `None` for token-held keys
+    pub fn private_key(&self) -> Option<Vec<u8>> {
+        match self {
+            RoleKey::Ed25519 { seed, .. } => Some(seed.to_vec()),
+            RoleKey::Rsa(key_pair) => Some(key_pair.pkcs8_der().to_vec()),
+            #[cfg(feature = "pkcs11")]
+            RoleKey::Token(_) => None,
+        }
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Result<Vec<u8>, TrustInitError> {
+        Ok(match self {
+            RoleKey::Ed25519 { key_pair, .. } => key_pair.sign(message).to_vec(),
+            RoleKey::Rsa(key_pair) => key_pair.sign(message)?,
+            #[cfg(feature = "pkcs11")]
+            RoleKey::Token(key) => key.sign(message)?,
+        })
+    }
+
+    /// `(token label, key label)` of a token-held key
+    pub fn token(&self) -> Option<(&str, &str)> {
+        match self {
+            #[cfg(feature = "pkcs11")]
+            RoleKey::Token(key) => Some((&key.config().token_label, &key.config().key_label)),
+            _ => None,
+        }
+    }
+
+    pub fn source(&self) -> &'static str {
+        if self.private_key().is_some() {
+            "generated"
+        } else {
+            "pkcs11"
+        }
+    }
+}
@@ -0,0 +1,60 @@
+// Path and File Name : /home/ransomeye/rebuild/ops/trust_init/src/sheet.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Printable fingerprint sheet for out-of-band verification of the keys produced by a trust ceremony
+
+use std::fmt::Write;
+
+use crypto::digest::Sha256;
+
+use crate::ceremony::CeremonyReport;
+
+/// SHA-256 of `bytes`, lowercase hex (manifest and key request format)
+pub fn fingerprint(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+/// `ABCD 1234 ...` in two lines of eight groups, for reading aloud
+pub fn format_fingerprint(fingerprint_hex: &str) -> String {
+    let groups: Vec<String> = fingerprint_hex
+        .to_uppercase()
+        .as_bytes()
+        .chunks(4)
+        .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+        .collect();
+    groups.chunks(8).map(|line| line.join(" ")).collect::<Vec<_>>().join("\n")
+}
+
+pub fn render(report: &CeremonyReport) -> String {
+    let manifest = &report.manifest;
+    let mut out = String::new();
+    let _ = writeln!(out, "RansomEye Trust Initialization - Fingerprint Sheet");
+    let _ = writeln!(out, "==================================================");
+    let _ = writeln!(out);
+    let _ = writeln!(out, "Deployment:   {}", manifest.deployment);
+    let _ = writeln!(out, "Created:      {}", manifest.created_at.to_rfc3339());
+    let _ = writeln!(out, "Crypto:       {}", manifest.crypto_backend);
+    let _ = writeln!(out);
+    let _ = writeln!(out, "Read each fingerprint to a second officer over an independent channel and");
+    let _ = writeln!(out, "compare it with the installed file before the deployment is trusted.");
+
+    for key in &manifest.keys {
+        let _ = writeln!(out);
+        let _ = writeln!(out, "[{}]  {}  ({})", key.role, key.algorithm, key.source);
+        let _ = writeln!(out, "  File:     {}", key.public_key_path.display());
+        let _ = writeln!(out, "  SHA-256:");
+        for line in format_fingerprint(&key.fingerprint_sha256).lines() {
+            let _ = writeln!(out, "    {}", line);
+        }
+    }
+
+    let _ = writeln!(out);
+    let _ = writeln!(out, "Trust manifest: {}", report.layout.manifest().display());
+    let _ = writeln!(out, "  SHA-256:");
+    for line in format_fingerprint(&report.manifest_sha256).lines() {
+        let _ = writeln!(out, "    {}", line);
+    }
+    let _ = writeln!(out);
+    let _ = writeln!(out, "Ceremony officer: ______________________  Signature: ______________  Date: __________");
+    let _ = writeln!(out, "Witness:          ______________________  Signature: ______________  Date: __________");
+    out
+}
@@ -0,0 +1,97 @@
+// Path and File Name : /home/ransomeye/rebuild/ops/trust_init/tests/trust_init_tests.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Tests for the key ceremony - trust store layout, key formats, manifest verification and fail-closed re-runs
+
+use std::fs;
+
+use crypto::signature::{verify_ed25519, verify_rsa_pss_sha256, Ed25519KeyPair, RsaPssKeyPair};
+use tempfile::TempDir;
+use trust_init::{CeremonyOptions, KeyRole, TrustInitError, TrustLayout};
+
+fn options(dir: &TempDir) -> CeremonyOptions {
+    let mut options = CeremonyOptions::new(TrustLayout::new(dir.path(), None), "test-site");
+    // RSA-4096 generation is slow in debug builds; the layout is the same
+    options.policy_key_bits = 2048;
+    options
+}
+
+#[test]
+fn test_ceremony_writes_layout_consumers_expect() {
+    let dir = TempDir::new().unwrap();
+    let options = options(&dir);
+    let report = trust_init::run(&options).unwrap();
+    let layout = &options.layout;
+
+    // Kernel root key and deception key: raw Ed25519, private seeds in the ceremony directory
+    let root_seed: [u8; 32] = fs::read(layout.private_key(KeyRole::Root)).unwrap().try_into().unwrap();
+    let root = Ed25519KeyPair::from_seed(&root_seed).unwrap();
+    assert_eq!(fs::read(layout.root_public_key()).unwrap(), root.public_key());
+    assert_eq!(fs::read(layout.deception_public_key()).unwrap().len(), 32);
+
+    // Policy trust store: DER RSAPublicKey named after the policy key id, PKCS#8 private key
+    let policy = RsaPssKeyPair::from_pkcs8(&fs::read(layout.private_key(KeyRole::Policy)).unwrap()).unwrap();
+    let trusted = fs::read(layout.policy_trust_dir().join("policy_root_v1.der")).unwrap();
+    let signature = policy.sign(b"policy").unwrap();
+    verify_rsa_pss_sha256(&trusted, b"policy", &signature).unwrap();
+
+    // Audit: online seed plus hex verifying key
+    let audit_seed: [u8; 32] = fs::read(layout.audit_signing_key()).unwrap().try_into().unwrap();
+    let audit = Ed25519KeyPair::from_seed(&audit_seed).unwrap();
+    assert_eq!(fs::read_to_string(layout.audit_public_key()).unwrap(), hex::encode(audit.public_key()));
+    verify_ed25519(&audit.public_key(), b"x", &audit.sign(b"x")).unwrap();
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(layout.private_key(KeyRole::Root)).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        let mode = fs::metadata(layout.audit_signing_key()).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    let manifest = trust_init::verify_manifest(&layout.manifest(), &layout.root_public_key()).unwrap();
+    assert_eq!(manifest.keys.len(), 4);
+    assert_eq!(manifest.deployment, "test-site");
+
+    let sheet = fs::read_to_string(layout.fingerprint_sheet()).unwrap();
+    for key in &report.manifest.keys {
+        let first_group = key.fingerprint_sha256[..4].to_uppercase();
+        assert!(sheet.contains(&format!("[{}]", key.role)));
+        assert!(sheet.contains(&first_group));
+    }
+    assert!(report.env_exports().iter().any(|(name, _)| name == "RANSOMEYE_ROOT_KEY_PATH"));
+}
+
+#[test]
+fn test_ceremony_refuses_to_overwrite() {
+    let dir = TempDir::new().unwrap();
+    let options = options(&dir);
+    trust_init::run(&options).unwrap();
+    let root_before = fs::read(options.layout.root_public_key()).unwrap();
+
+    let err = trust_init::run(&options).unwrap_err();
+    assert!(matches!(err, TrustInitError::AlreadyInitialized(_)));
+    assert_eq!(fs::read(options.layout.root_public_key()).unwrap(), root_before);
+}
+
+#[cfg(not(feature = "pkcs11"))]
+#[test]
+fn test_hsm_role_without_pkcs11_fails_before_writing() {
+    let dir = TempDir::new().unwrap();
+    let mut options = options(&dir);
+    options.hsm_roles = vec![KeyRole::Audit];
+
+    let err = trust_init::run(&options).unwrap_err();
+    assert!(matches!(err, TrustInitError::Hsm(_)));
+    assert!(!options.layout.root_public_key().exists());
+}
+
+#[test]
+fn test_manifest_detects_replaced_key() {
+    let dir = TempDir::new().unwrap();
+    let options = options(&dir);
+    trust_init::run(&options).unwrap();
+
+    fs::write(options.layout.deception_public_key(), [7u8; 32]).unwrap();
+    assert!(trust_init::verify_manifest(&options.layout.manifest(), &options.layout.root_public_key()).is_err());
+}
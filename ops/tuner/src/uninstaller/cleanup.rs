@@ -105,6 +105,7 @@ impl CleanupManager {
         let services = vec![
             "ransomeye-core.service",
             "ransomeye-ingestion.service",
+            "ransomeye-ingestion.socket",
             "ransomeye-correlation.service",
             "ransomeye-policy.service",
             "ransomeye-enforcement.service",
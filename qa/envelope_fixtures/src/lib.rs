@@ -0,0 +1,138 @@
+// Path and File Name : /home/ransomeye/rebuild/qa/envelope_fixtures/src/lib.rs
+// Author: nXxBku0CKFAJCBN3X1g3bQk7OxYQylg8CMw1iGsq7gU
+// Details of functionality of this file: Golden signed event envelopes per schema version - producer canonical bytes, payload and stored envelope hashes, and the fields core extracts - for the agent and ingest round-trip tests
+
+/*
+ * Envelope Golden Fixtures
+ *
+ * Each sample under fixtures/v<schema_version>/ is a pair:
+ *   - <name>.envelope.json: the exact bytes the producer serializes and hashes into payload_hash
+ *     (no trailing newline; a reformatted file is a different envelope)
+ *   - <name>.expected.json: payload_hash, envelope_sha256 (the envelope as sent in the SignedEvent
+ *     and stored by ingest; producers embed the canonical bytes verbatim, so it equals
+ *     payload_hash) and the fields ingest extracts from the envelope
+ *
+ * The agent tests re-serialize every sample through the agent's own envelope types and must get
+ * the same bytes and hashes; the ingest tests parse every sample through the signed event views
+ * and must extract the same fields. A schema change that breaks either side fails both crates'
+ * tests. Samples of a released schema version are never edited: a new envelope shape gets a new
+ * schema version directory.
+ */
+
+use serde_json::Value;
+
+/// Envelope schema versions with golden samples (absent schema_version = 1)
+pub const SCHEMA_VERSIONS: &[u32] = &[1];
+
+/// Signature carried by fixture SignedEvents (base64; ingest only decodes it)
+pub const FIXTURE_SIGNATURE: &str = "Z29sZGVuLWZpeHR1cmUtc2lnbmF0dXJl";
+
+/// Signer id of fixture SignedEvents
+pub const FIXTURE_SIGNER_ID: &str = "host-7f3a";
+
+/// One golden envelope of a schema version.
+#[derive(Debug, Clone, Copy)]
+pub struct Golden {
+    pub name: &'static str,
+    pub schema_version: u32,
+    /// Producer component (linux_agent)
+    pub producer: &'static str,
+    /// Producer canonical envelope bytes
+    pub envelope: &'static str,
+    expected: &'static str,
+}
+
+macro_rules! golden {
+    ($version:literal, $producer:literal, $name:literal) => {
+        Golden {
+            name: $name,
+            schema_version: $version,
+            producer: $producer,
+            envelope: include_str!(concat!("../fixtures/v", $version, "/", $name, ".envelope.json")),
+            expected: include_str!(concat!("../fixtures/v", $version, "/", $name, ".expected.json")),
+        }
+    };
+}
+
+const GOLDEN: &[Golden] = &[
+    golden!(1, "linux_agent", "linux_process_exec"),
+    golden!(1, "linux_agent", "linux_file_rename"),
+    golden!(1, "linux_agent", "linux_network_connect"),
+    golden!(1, "linux_agent", "linux_agent_stats"),
+];
+
+/// Every golden sample, all schema versions
+pub fn all() -> &'static [Golden] {
+    GOLDEN
+}
+
+/// Golden samples of one producer
+pub fn for_producer(producer: &str) -> impl Iterator<Item = &'static Golden> + '_ {
+    GOLDEN.iter().filter(move |g| g.producer == producer)
+}
+
+impl Golden {
+    /// Expected hashes and extracted fields
+    pub fn expected(&self) -> Value {
+        serde_json::from_str(self.expected)
+            .unwrap_or_else(|e| panic!("{}: invalid expected.json: {}", self.name, e))
+    }
+
+    /// SHA-256 hex of the producer canonical bytes, as sent in payload_hash
+    pub fn payload_hash(&self) -> String {
+        self.expected_str("payload_hash")
+    }
+
+    /// SHA-256 hex of the envelope bytes as sent in the SignedEvent (what ingest hashes and stores)
+    pub fn envelope_sha256(&self) -> String {
+        self.expected_str("envelope_sha256")
+    }
+
+    /// The SignedEvent body a producer posts for this envelope: the canonical bytes verbatim,
+    /// payload_hash over them, fixture signature
+    pub fn signed_event_body(&self) -> Vec<u8> {
+        format!(
+            r#"{{"envelope":{},"payload_hash":"{}","signature":"{}","signer_id":"{}"}}"#,
+            self.envelope,
+            self.payload_hash(),
+            FIXTURE_SIGNATURE,
+            FIXTURE_SIGNER_ID
+        )
+        .into_bytes()
+    }
+
+    fn expected_str(&self, key: &str) -> String {
+        match self.expected().get(key).and_then(Value::as_str) {
+            Some(s) => s.to_string(),
+            None => panic!("{}: expected.json has no {}", self.name, key),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn test_hashes_match_fixture_bytes() {
+        for golden in all() {
+            assert_eq!(hex::encode(Sha256::digest(golden.envelope.as_bytes())), golden.payload_hash(), "{}", golden.name);
+            assert_eq!(golden.envelope_sha256(), golden.payload_hash(), "{}", golden.name);
+            let body: Value = serde_json::from_slice(&golden.signed_event_body()).unwrap();
+            assert_eq!(body["envelope"], serde_json::from_str::<Value>(golden.envelope).unwrap(), "{}", golden.name);
+        }
+    }
+
+    #[test]
+    fn test_every_schema_version_has_samples() {
+        for version in SCHEMA_VERSIONS {
+            assert!(all().iter().any(|g| g.schema_version == *version), "no golden samples for v{}", version);
+        }
+        for golden in all() {
+            assert!(SCHEMA_VERSIONS.contains(&golden.schema_version), "{}", golden.name);
+            assert!(!golden.envelope.ends_with(char::is_whitespace), "{}: trailing whitespace", golden.name);
+            assert_eq!(golden.expected()["schema_version"], golden.schema_version, "{}", golden.name);
+        }
+    }
+}
@@ -140,9 +140,13 @@ CREATE TABLE IF NOT EXISTS agents (
   decommissioned_at      timestamptz NULL,
   is_active              boolean NOT NULL DEFAULT true,
   tags                   jsonb NULL,
+  residency_region       text NULL,
+  signing_public_key     bytea NULL,
   created_at             timestamptz NOT NULL DEFAULT now(),
   updated_at             timestamptz NOT NULL DEFAULT now(),
-  CONSTRAINT agents_type_chk CHECK (agent_type IN ('linux_agent','windows_agent','dpi_probe','unknown'))
+  CONSTRAINT agents_type_chk CHECK (agent_type IN ('linux_agent','windows_agent','dpi_probe','unknown')),
+  CONSTRAINT agents_residency_region_chk CHECK (residency_region IS NULL OR residency_region ~ '^[a-z][a-z0-9-]{0,31}$'),
+  CONSTRAINT agents_signing_public_key_chk CHECK (signing_public_key IS NULL OR length(signing_public_key) = 32)
 );
 
 COMMENT ON TABLE agents IS
@@ -169,11 +173,987 @@ COMMENT ON COLUMN agents.last_seen_at IS 'Most recent heartbeat/telemetry observ
 COMMENT ON COLUMN agents.decommissioned_at IS 'If set, the time the agent was retired/decommissioned.';
 COMMENT ON COLUMN agents.is_active IS 'Operational active flag for scheduling/enforcement decisions.';
 COMMENT ON COLUMN agents.tags IS 'Optional structured tags for grouping/filtering agents (JSONB justified for flexible metadata).';
+COMMENT ON COLUMN agents.residency_region IS 'Data residency region the agent is pinned to at enrollment (never changed by re-enrollment); ingest refuses cross-region delivery unless policy allows the route. NULL for agents enrolled before residency tagging.';
+COMMENT ON COLUMN agents.signing_public_key IS 'Raw 32-byte Ed25519 public key registered at enrollment (replaced only by key rotation); ingest verifies event signatures against it when the trust store has no key for the signer. NULL until the agent registers a key.';
 COMMENT ON COLUMN agents.created_at IS 'Row creation timestamp.';
 COMMENT ON COLUMN agents.updated_at IS 'Row last update timestamp (mutable table).';
 
 CREATE INDEX IF NOT EXISTS idx_agents_last_seen_at ON agents (last_seen_at);
 CREATE INDEX IF NOT EXISTS idx_agents_type ON agents (agent_type);
+CREATE INDEX IF NOT EXISTS idx_agents_residency_region ON agents (residency_region) WHERE residency_region IS NOT NULL;
+
+-- agent_api_tokens: per-agent bearer tokens for ingest authentication (interim until mTLS everywhere)
+CREATE TABLE IF NOT EXISTS agent_api_tokens (
+  token_id               uuid PRIMARY KEY,
+  agent_id               uuid NOT NULL REFERENCES agents(agent_id) ON UPDATE RESTRICT ON DELETE RESTRICT,
+  token_sha256           bytea NOT NULL,
+  issued_at              timestamptz NOT NULL DEFAULT now(),
+  expires_at             timestamptz NOT NULL,
+  last_used_at           timestamptz NULL,
+  revoked_at             timestamptz NULL,
+  revoked_reason         text NULL,
+  rotated_from_token_id  uuid NULL REFERENCES agent_api_tokens(token_id) ON UPDATE RESTRICT ON DELETE SET NULL,
+  created_at             timestamptz NOT NULL DEFAULT now(),
+  CONSTRAINT agent_api_tokens_sha256_len_chk CHECK (octet_length(token_sha256) = 32),
+  CONSTRAINT agent_api_tokens_expiry_chk CHECK (expires_at > issued_at),
+  CONSTRAINT agent_api_tokens_revoked_reason_chk CHECK (revoked_at IS NULL OR revoked_reason IS NOT NULL)
+);
+
+COMMENT ON TABLE agent_api_tokens IS
+'Purpose: Signed per-agent bearer tokens issued at enrollment; authoritative record for token rotation, expiry, and revocation.\n'
+'Writing module(s): Core Engine ingestion (enrollment, rotation, revocation).\n'
+'Reading module(s): Core Engine ingestion (token middleware), Validator.\n'
+'Retention expectation: long.';
+
+COMMENT ON COLUMN agent_api_tokens.token_id IS 'Primary key. Token identifier embedded in the token (not secret).';
+COMMENT ON COLUMN agent_api_tokens.agent_id IS 'FK to agents.agent_id the token authenticates.';
+COMMENT ON COLUMN agent_api_tokens.token_sha256 IS 'SHA-256 of the full token string (plaintext token is never stored).';
+COMMENT ON COLUMN agent_api_tokens.issued_at IS 'Issuance timestamp.';
+COMMENT ON COLUMN agent_api_tokens.expires_at IS 'Expiry timestamp (shortened to the rotation grace window when rotated).';
+COMMENT ON COLUMN agent_api_tokens.last_used_at IS 'Most recent successful authentication with this token.';
+COMMENT ON COLUMN agent_api_tokens.revoked_at IS 'If set, the token is revoked and rejected regardless of expiry.';
+COMMENT ON COLUMN agent_api_tokens.revoked_reason IS 'Operator-supplied revocation reason (required when revoked).';
+COMMENT ON COLUMN agent_api_tokens.rotated_from_token_id IS 'Token this one replaced via rotation (optional).';
+COMMENT ON COLUMN agent_api_tokens.created_at IS 'Row creation timestamp.';
+
+CREATE INDEX IF NOT EXISTS idx_agent_api_tokens_agent_id ON agent_api_tokens (agent_id);
+CREATE INDEX IF NOT EXISTS idx_agent_api_tokens_expires_at ON agent_api_tokens (expires_at);
+
+-- agent_key_pins: signing key pinned per agent (historical identity binding)
+CREATE TABLE IF NOT EXISTS agent_key_pins (
+  agent_id               uuid PRIMARY KEY REFERENCES agents(agent_id) ON UPDATE RESTRICT ON DELETE RESTRICT,
+  signer_id              text NOT NULL,
+  pinned_via             text NOT NULL,
+  previous_signer_id     text NULL,
+  previous_valid_until   timestamptz NULL,
+  rotation_reason        text NULL,
+  created_at             timestamptz NOT NULL DEFAULT now(),
+  updated_at             timestamptz NOT NULL DEFAULT now(),
+  CONSTRAINT agent_key_pins_signer_nonempty_chk CHECK (length(signer_id) > 0),
+  CONSTRAINT agent_key_pins_pinned_via_chk CHECK (pinned_via IN ('first_use', 'enrollment', 'operator_rotation')),
+  CONSTRAINT agent_key_pins_rotation_reason_chk CHECK (pinned_via <> 'operator_rotation' OR rotation_reason IS NOT NULL)
+);
+
+COMMENT ON TABLE agent_key_pins IS
+'Purpose: Signing key (signer_id) each agent is bound to; a different key from the same agent is a detection and is rejected in enforce mode.\n'
+'Writing module(s): Core Engine ingestion (first use, enrollment, operator-approved rotation).\n'
+'Reading module(s): Core Engine ingestion (per-event key check), UI, Validator.\n'
+'Retention expectation: long.';
+
+COMMENT ON COLUMN agent_key_pins.agent_id IS 'Primary key. FK to agents.agent_id the key is pinned to.';
+COMMENT ON COLUMN agent_key_pins.signer_id IS 'Pinned key identifier (SignedEvent.signer_id).';
+COMMENT ON COLUMN agent_key_pins.pinned_via IS 'How the current key was pinned: first_use, enrollment, or operator_rotation.';
+COMMENT ON COLUMN agent_key_pins.previous_signer_id IS 'Key replaced by the last operator-approved rotation (optional).';
+COMMENT ON COLUMN agent_key_pins.previous_valid_until IS 'End of the rotation grace window during which previous_signer_id is still accepted.';
+COMMENT ON COLUMN agent_key_pins.rotation_reason IS 'Operator-supplied rotation reason (required for operator_rotation).';
+COMMENT ON COLUMN agent_key_pins.created_at IS 'Row creation timestamp (first pin).';
+COMMENT ON COLUMN agent_key_pins.updated_at IS 'Last rotation timestamp.';
+
+-- identity_conflicts: one component identity used concurrently from different origins (duplicate agent-id)
+CREATE TABLE IF NOT EXISTS identity_conflicts (
+  conflict_id            uuid PRIMARY KEY,
+  component_type         text NOT NULL,
+  component_identity     text NOT NULL,
+  agent_id               uuid NOT NULL REFERENCES agents(agent_id) ON UPDATE RESTRICT ON DELETE RESTRICT,
+  first_origin           jsonb NOT NULL,
+  conflicting_origin     jsonb NOT NULL,
+  detection_id           uuid NOT NULL,
+  opened_at              timestamptz NOT NULL,
+  status                 text NOT NULL DEFAULT 'open',
+  resolution             text NULL,
+  resolution_reason      text NULL,
+  resolved_at            timestamptz NULL,
+  CONSTRAINT identity_conflicts_status_chk CHECK (status IN ('open', 'resolved')),
+  CONSTRAINT identity_conflicts_resolution_chk CHECK (
+    (status = 'open' AND resolution IS NULL AND resolved_at IS NULL) OR
+    (status = 'resolved' AND resolution IN ('release', 'discard') AND resolved_at IS NOT NULL)
+  )
+);
+
+COMMENT ON TABLE identity_conflicts IS
+'Purpose: Component identities seen from two origins (source IP / host id) within the conflict window; events of an open conflict are quarantined.\n'
+'Writing module(s): Core Engine ingestion (conflict detection, operator resolution).\n'
+'Reading module(s): Core Engine ingestion (quarantine state at startup), UI, Validator.\n'
+'Retention expectation: long.';
+
+COMMENT ON COLUMN identity_conflicts.conflict_id IS 'Primary key.';
+COMMENT ON COLUMN identity_conflicts.component_type IS 'Producer type as labelled by ingest (linux_agent, dpi_probe, ...).';
+COMMENT ON COLUMN identity_conflicts.component_identity IS 'Envelope component_id used from both origins.';
+COMMENT ON COLUMN identity_conflicts.agent_id IS 'FK to agents.agent_id the identity resolves to.';
+COMMENT ON COLUMN identity_conflicts.first_origin IS 'Origin already using the identity: {source_ip, host_id}.';
+COMMENT ON COLUMN identity_conflicts.conflicting_origin IS 'Second origin that opened the conflict: {source_ip, host_id}.';
+COMMENT ON COLUMN identity_conflicts.detection_id IS 'detection_results row recorded for the conflict.';
+COMMENT ON COLUMN identity_conflicts.opened_at IS 'When the conflict was detected.';
+COMMENT ON COLUMN identity_conflicts.status IS 'open (events quarantined) or resolved.';
+COMMENT ON COLUMN identity_conflicts.resolution IS 'Operator decision for the quarantined events: release or discard.';
+COMMENT ON COLUMN identity_conflicts.resolution_reason IS 'Operator-supplied justification.';
+COMMENT ON COLUMN identity_conflicts.resolved_at IS 'When the operator resolved the conflict.';
+
+CREATE UNIQUE INDEX IF NOT EXISTS identity_conflicts_open_uniq_idx
+ON identity_conflicts (component_type, component_identity) WHERE status = 'open';
+
+-- quarantined_events: signed events held back while their identity is in conflict
+CREATE TABLE IF NOT EXISTS quarantined_events (
+  quarantine_id          uuid PRIMARY KEY,
+  conflict_id            uuid NOT NULL REFERENCES identity_conflicts(conflict_id) ON UPDATE RESTRICT ON DELETE RESTRICT,
+  component_type         text NOT NULL,
+  component_identity     text NOT NULL,
+  message_id             text NOT NULL,
+  source_ip              inet NULL,
+  host_id                text NULL,
+  received_at            timestamptz NOT NULL DEFAULT now(),
+  signed_event           jsonb NOT NULL,
+  signed_event_sha256    bytea NOT NULL,
+  status                 text NOT NULL DEFAULT 'pending',
+  CONSTRAINT quarantined_events_status_chk CHECK (status IN ('pending', 'released', 'discarded')),
+  CONSTRAINT quarantined_events_sha256_len_chk CHECK (octet_length(signed_event_sha256) = 32)
+);
+
+COMMENT ON TABLE quarantined_events IS
+'Purpose: Pending table for events whose component identity has an open identity conflict; never written to raw_events while pending.\n'
+'Writing module(s): Core Engine ingestion.\n'
+'Reading module(s): UI, Validator, operator replay tooling (released rows).\n'
+'Retention expectation: medium.';
+
+COMMENT ON COLUMN quarantined_events.quarantine_id IS 'Primary key.';
+COMMENT ON COLUMN quarantined_events.conflict_id IS 'FK to identity_conflicts.conflict_id that caused the quarantine.';
+COMMENT ON COLUMN quarantined_events.component_type IS 'Producer type as labelled by ingest.';
+COMMENT ON COLUMN quarantined_events.component_identity IS 'Envelope component_id.';
+COMMENT ON COLUMN quarantined_events.message_id IS 'Envelope event_id.';
+COMMENT ON COLUMN quarantined_events.source_ip IS 'Peer address the event was received from.';
+COMMENT ON COLUMN quarantined_events.host_id IS 'Producer-reported host id (envelope host_id, optional).';
+COMMENT ON COLUMN quarantined_events.received_at IS 'When ingest quarantined the event.';
+COMMENT ON COLUMN quarantined_events.signed_event IS 'Signed event as received (envelope, signature, signer_id).';
+COMMENT ON COLUMN quarantined_events.signed_event_sha256 IS 'SHA-256 of the received request body.';
+COMMENT ON COLUMN quarantined_events.status IS 'pending until the conflict is resolved, then released or discarded.';
+
+CREATE INDEX IF NOT EXISTS idx_quarantined_events_conflict_id ON quarantined_events (conflict_id);
+
+-- asset_tags: operator-maintained business context per asset (hostname and/or IP)
+CREATE TABLE IF NOT EXISTS asset_tags (
+  asset_tag_id           uuid PRIMARY KEY,
+  hostname               text NULL,
+  ip                     inet NULL,
+  criticality            text NOT NULL,
+  owner                  text NULL,
+  environment            text NULL,
+  source                 text NOT NULL,
+  imported_at            timestamptz NOT NULL DEFAULT now(),
+  CONSTRAINT asset_tags_target_chk CHECK (hostname IS NOT NULL OR ip IS NOT NULL),
+  CONSTRAINT asset_tags_hostname_lower_chk CHECK (hostname IS NULL OR (length(hostname) > 0 AND hostname = lower(hostname))),
+  CONSTRAINT asset_tags_criticality_chk CHECK (criticality IN ('low', 'medium', 'high', 'critical'))
+);
+
+COMMENT ON TABLE asset_tags IS
+'Purpose: Asset criticality, owner and environment tags used to enrich and prioritize detections.\n'
+'Writing module(s): ransomeye_asset_tags (CSV import).\n'
+'Reading module(s): Core Engine correlation (detection enrichment), replay, UI.\n'
+'Retention expectation: long.';
+
+COMMENT ON COLUMN asset_tags.asset_tag_id IS 'Primary key.';
+COMMENT ON COLUMN asset_tags.hostname IS 'Lower-cased hostname (short name or FQDN); optional when ip is set.';
+COMMENT ON COLUMN asset_tags.ip IS 'Asset IP address; optional when hostname is set.';
+COMMENT ON COLUMN asset_tags.criticality IS 'Business criticality: low, medium, high or critical.';
+COMMENT ON COLUMN asset_tags.owner IS 'Owning team or person (optional, used for notification routing).';
+COMMENT ON COLUMN asset_tags.environment IS 'Deployment environment, e.g. production or staging (optional).';
+COMMENT ON COLUMN asset_tags.source IS 'Import source (CSV file name).';
+COMMENT ON COLUMN asset_tags.imported_at IS 'When the row was last imported.';
+
+CREATE UNIQUE INDEX IF NOT EXISTS asset_tags_hostname_uniq_idx ON asset_tags (hostname) WHERE hostname IS NOT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS asset_tags_ip_uniq_idx ON asset_tags (ip) WHERE ip IS NOT NULL;
+
+-- webhook_subscriptions: operator-registered receivers of lifecycle events
+CREATE TABLE IF NOT EXISTS webhook_subscriptions (
+  webhook_id             uuid PRIMARY KEY,
+  url                    text NOT NULL,
+  secret                 bytea NOT NULL,
+  event_types            text[] NOT NULL,
+  description            text NULL,
+  enabled                boolean NOT NULL DEFAULT true,
+  created_at             timestamptz NOT NULL DEFAULT now(),
+  disabled_at            timestamptz NULL,
+  CONSTRAINT webhook_subscriptions_url_chk CHECK (url ~ '^https?://'),
+  CONSTRAINT webhook_subscriptions_secret_len_chk CHECK (octet_length(secret) >= 32),
+  CONSTRAINT webhook_subscriptions_event_types_chk CHECK (cardinality(event_types) > 0)
+);
+
+COMMENT ON TABLE webhook_subscriptions IS
+'Purpose: Webhook endpoints with their signing secret and event-type filter.\n'
+'Writing module(s): Core Engine ingestion (admin API).\n'
+'Reading module(s): Core Engine ingestion and retention (delivery fan-out), orchestrator webhook dispatcher.\n'
+'Retention expectation: long.';
+
+COMMENT ON COLUMN webhook_subscriptions.webhook_id IS 'Primary key.';
+COMMENT ON COLUMN webhook_subscriptions.url IS 'Receiver URL (https; http only for loopback).';
+COMMENT ON COLUMN webhook_subscriptions.secret IS 'HMAC-SHA256 signing secret (returned once at registration).';
+COMMENT ON COLUMN webhook_subscriptions.event_types IS 'Subscribed event types (agent.enrolled, detection.created, retention.run, retention.alert, disk.quota, db.capacity, operator.break_glass) or * for all.';
+COMMENT ON COLUMN webhook_subscriptions.description IS 'Operator note (optional).';
+COMMENT ON COLUMN webhook_subscriptions.enabled IS 'Disabled subscriptions receive no new deliveries.';
+COMMENT ON COLUMN webhook_subscriptions.created_at IS 'Registration timestamp.';
+COMMENT ON COLUMN webhook_subscriptions.disabled_at IS 'When the subscription was disabled.';
+
+-- webhook_deliveries: delivery outbox and history (one row per event per subscription)
+CREATE TABLE IF NOT EXISTS webhook_deliveries (
+  delivery_id            uuid PRIMARY KEY,
+  webhook_id             uuid NOT NULL REFERENCES webhook_subscriptions(webhook_id) ON UPDATE RESTRICT ON DELETE RESTRICT,
+  event_id               uuid NOT NULL,
+  event_type             text NOT NULL,
+  payload                jsonb NOT NULL,
+  status                 text NOT NULL DEFAULT 'pending',
+  attempts               integer NOT NULL DEFAULT 0,
+  next_attempt_at        timestamptz NOT NULL DEFAULT now(),
+  last_status_code       integer NULL,
+  last_error             text NULL,
+  created_at             timestamptz NOT NULL DEFAULT now(),
+  delivered_at           timestamptz NULL,
+  CONSTRAINT webhook_deliveries_status_chk CHECK (status IN ('pending', 'delivered', 'dead')),
+  CONSTRAINT webhook_deliveries_attempts_chk CHECK (attempts >= 0),
+  CONSTRAINT webhook_deliveries_delivered_chk CHECK ((status = 'delivered') = (delivered_at IS NOT NULL))
+);
+
+COMMENT ON TABLE webhook_deliveries IS
+'Purpose: Outbox and history of webhook deliveries; status dead is the dead-letter set.\n'
+'Writing module(s): Core Engine ingestion and retention (enqueue), orchestrator webhook dispatcher (attempts), ingestion admin API (re-drive).\n'
+'Reading module(s): Orchestrator webhook dispatcher, ingestion admin API (delivery history), UI.\n'
+'Retention expectation: medium.';
+
+COMMENT ON COLUMN webhook_deliveries.delivery_id IS 'Primary key; sent as X-RansomEye-Delivery (stable across retries).';
+COMMENT ON COLUMN webhook_deliveries.webhook_id IS 'FK to webhook_subscriptions.webhook_id.';
+COMMENT ON COLUMN webhook_deliveries.event_id IS 'Lifecycle event id (shared by the deliveries of one event).';
+COMMENT ON COLUMN webhook_deliveries.event_type IS 'Lifecycle event type.';
+COMMENT ON COLUMN webhook_deliveries.payload IS 'Event JSON delivered as the request body.';
+COMMENT ON COLUMN webhook_deliveries.status IS 'pending, delivered, or dead (attempts exhausted).';
+COMMENT ON COLUMN webhook_deliveries.attempts IS 'Failed attempts so far (a delivered row counts its final attempt).';
+COMMENT ON COLUMN webhook_deliveries.next_attempt_at IS 'Earliest next attempt; also the dispatcher lease while an attempt is in flight.';
+COMMENT ON COLUMN webhook_deliveries.last_status_code IS 'HTTP status of the last attempt (NULL on transport error).';
+COMMENT ON COLUMN webhook_deliveries.last_error IS 'Transport or HTTP error of the last failed attempt.';
+COMMENT ON COLUMN webhook_deliveries.created_at IS 'When the event was enqueued.';
+COMMENT ON COLUMN webhook_deliveries.delivered_at IS 'When a 2xx response was received.';
+
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_due ON webhook_deliveries (next_attempt_at) WHERE status = 'pending';
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_webhook_created ON webhook_deliveries (webhook_id, created_at);
+
+-- ingest_outbox: post-commit messages written in the ingest transaction (transactional outbox)
+CREATE TABLE IF NOT EXISTS ingest_outbox (
+  outbox_id              uuid PRIMARY KEY,
+  topic                  text NOT NULL,
+  aggregate_id           uuid NOT NULL,
+  payload                jsonb NOT NULL,
+  status                 text NOT NULL DEFAULT 'pending',
+  attempts               integer NOT NULL DEFAULT 0,
+  next_attempt_at        timestamptz NOT NULL DEFAULT now(),
+  last_error             text NULL,
+  created_at             timestamptz NOT NULL DEFAULT now(),
+  published_at           timestamptz NULL,
+  CONSTRAINT ingest_outbox_status_chk CHECK (status IN ('pending', 'published')),
+  CONSTRAINT ingest_outbox_attempts_chk CHECK (attempts >= 0),
+  CONSTRAINT ingest_outbox_published_chk CHECK ((status = 'published') = (published_at IS NOT NULL))
+);
+
+COMMENT ON TABLE ingest_outbox IS
+'Purpose: Messages announcing committed ingest work, inserted in the same transaction as the telemetry so none is lost or sent for a rolled-back event.\n'
+'Writing module(s): Core Engine ingestion (enqueue in the ingest transaction; relay claim, publish and mark).\n'
+'Reading module(s): Core Engine ingestion outbox relay.\n'
+'Retention expectation: short (published rows purged after RANSOMEYE_INGEST_OUTBOX_RETAIN_SECS).';
+
+COMMENT ON COLUMN ingest_outbox.outbox_id IS 'Primary key; carried in the published message so consumers can dedupe redeliveries.';
+COMMENT ON COLUMN ingest_outbox.topic IS 'Message topic (telemetry.accepted).';
+COMMENT ON COLUMN ingest_outbox.aggregate_id IS 'Id of the row the message describes (raw_events.raw_event_id for telemetry.accepted).';
+COMMENT ON COLUMN ingest_outbox.payload IS 'Message body JSON.';
+COMMENT ON COLUMN ingest_outbox.status IS 'pending until the relay has published the message, then published.';
+COMMENT ON COLUMN ingest_outbox.attempts IS 'Failed publish attempts so far.';
+COMMENT ON COLUMN ingest_outbox.next_attempt_at IS 'Earliest next publish; also the relay lease while a publish is in flight.';
+COMMENT ON COLUMN ingest_outbox.last_error IS 'Error of the last failed publish.';
+COMMENT ON COLUMN ingest_outbox.created_at IS 'When the message was enqueued (ingest transaction time).';
+COMMENT ON COLUMN ingest_outbox.published_at IS 'When the relay marked the message published.';
+
+CREATE INDEX IF NOT EXISTS idx_ingest_outbox_due ON ingest_outbox (next_attempt_at) WHERE status = 'pending';
+CREATE INDEX IF NOT EXISTS idx_ingest_outbox_published ON ingest_outbox (published_at) WHERE status = 'published';
+
+-- telemetry_drops_daily: events lost per agent, UTC day, origin and reason
+CREATE TABLE IF NOT EXISTS telemetry_drops_daily (
+  agent_id               uuid NOT NULL REFERENCES agents(agent_id) ON UPDATE RESTRICT ON DELETE RESTRICT,
+  drop_day               date NOT NULL,
+  origin                 text NOT NULL,
+  reason                 text NOT NULL,
+  dropped                bigint NOT NULL DEFAULT 0,
+  first_recorded_at      timestamptz NOT NULL DEFAULT now(),
+  last_recorded_at       timestamptz NOT NULL DEFAULT now(),
+  PRIMARY KEY (agent_id, drop_day, origin, reason),
+  CONSTRAINT telemetry_drops_daily_origin_chk CHECK (origin IN ('agent', 'ingest')),
+  CONSTRAINT telemetry_drops_daily_dropped_chk CHECK (dropped >= 0)
+);
+
+COMMENT ON TABLE telemetry_drops_daily IS
+'Purpose: Telemetry completeness - events dropped on the agent (reported in agent_stats) or refused by ingest, summed per agent and day.\n'
+'Writing module(s): Core Engine ingestion (agent reports in the ingest transaction; periodic flush of ingest refusals).\n'
+'Reading module(s): Core Engine ingestion admin API (GET /admin/drops), reporting.\n'
+'Retention expectation: long.';
+
+COMMENT ON COLUMN telemetry_drops_daily.agent_id IS 'Agent whose telemetry was lost.';
+COMMENT ON COLUMN telemetry_drops_daily.drop_day IS 'UTC day (agent_stats envelope time for agent drops, refusal time for ingest drops).';
+COMMENT ON COLUMN telemetry_drops_daily.origin IS 'agent (dropped before delivery) or ingest (refused by ingest).';
+COMMENT ON COLUMN telemetry_drops_daily.reason IS 'Drop reason (e.g. queue_full, rate_limited, spool_evicted, invalid).';
+COMMENT ON COLUMN telemetry_drops_daily.dropped IS 'Events dropped on this day.';
+COMMENT ON COLUMN telemetry_drops_daily.first_recorded_at IS 'When the first drop of the day was recorded.';
+COMMENT ON COLUMN telemetry_drops_daily.last_recorded_at IS 'When the count was last increased.';
+
+CREATE INDEX IF NOT EXISTS idx_telemetry_drops_daily_day ON telemetry_drops_daily (drop_day);
+
+-- agent_drop_counters: last cumulative drop counter per agent run and reason
+CREATE TABLE IF NOT EXISTS agent_drop_counters (
+  agent_id               uuid NOT NULL REFERENCES agents(agent_id) ON UPDATE RESTRICT ON DELETE RESTRICT,
+  run_id                 text NOT NULL,
+  reason                 text NOT NULL,
+  cumulative             bigint NOT NULL,
+  updated_at             timestamptz NOT NULL DEFAULT now(),
+  PRIMARY KEY (agent_id, run_id, reason),
+  CONSTRAINT agent_drop_counters_cumulative_chk CHECK (cumulative >= 0)
+);
+
+COMMENT ON TABLE agent_drop_counters IS
+'Purpose: Cursor over the cumulative drop counters in agent_stats reports, so only their increase is added to telemetry_drops_daily.\n'
+'Writing module(s): Core Engine ingestion (in the ingest transaction of the report).\n'
+'Reading module(s): Core Engine ingestion.\n'
+'Retention expectation: short (runs not reported for RANSOMEYE_INGEST_DROP_COUNTER_RETAIN_SECS are purged).';
+
+COMMENT ON COLUMN agent_drop_counters.agent_id IS 'Reporting agent.';
+COMMENT ON COLUMN agent_drop_counters.run_id IS 'Agent run (new per agent start) the counters belong to.';
+COMMENT ON COLUMN agent_drop_counters.reason IS 'Drop reason.';
+COMMENT ON COLUMN agent_drop_counters.cumulative IS 'Highest cumulative count reported for this run and reason.';
+COMMENT ON COLUMN agent_drop_counters.updated_at IS 'When a report of this run was last applied.';
+
+CREATE INDEX IF NOT EXISTS idx_agent_drop_counters_updated ON agent_drop_counters (updated_at);
+
+-- host_inventory: latest host facts reported by each agent
+CREATE TABLE IF NOT EXISTS host_inventory (
+  agent_id               uuid PRIMARY KEY REFERENCES agents(agent_id) ON UPDATE RESTRICT ON DELETE RESTRICT,
+  hostname               text,
+  os_name                text,
+  os_version             text,
+  kernel_release         text NOT NULL,
+  kernel_version         text,
+  arch                   text NOT NULL,
+  cpu_model              text,
+  cpu_cores              integer NOT NULL,
+  memory_total_bytes     bigint NOT NULL,
+  security_tools         jsonb NOT NULL DEFAULT '[]'::jsonb,
+  disk_encryption        text NOT NULL,
+  encrypted_volumes      jsonb NOT NULL DEFAULT '[]'::jsonb,
+  source_event_id        text NOT NULL,
+  collected_at           timestamptz NOT NULL,
+  received_at            timestamptz NOT NULL DEFAULT now(),
+  CONSTRAINT host_inventory_cpu_cores_chk CHECK (cpu_cores >= 0),
+  CONSTRAINT host_inventory_memory_chk CHECK (memory_total_bytes >= 0),
+  CONSTRAINT host_inventory_disk_encryption_chk CHECK (disk_encryption IN ('encrypted', 'partial', 'none', 'unknown'))
+);
+
+COMMENT ON TABLE host_inventory IS
+'Purpose: Fleet inventory - OS, kernel, CPU/RAM, installed security tooling and disk encryption of every host, for vulnerability and coverage analysis.\n'
+'Writing module(s): Core Engine ingestion (host_inventory reports, in the ingest transaction of the report).\n'
+'Reading module(s): Core Engine ingestion admin API (GET /admin/fleet/inventory), reporting.\n'
+'Retention expectation: long (one row per agent, replaced by newer reports).';
+
+COMMENT ON COLUMN host_inventory.agent_id IS 'Reporting agent.';
+COMMENT ON COLUMN host_inventory.hostname IS 'Hostname as seen by the agent.';
+COMMENT ON COLUMN host_inventory.os_name IS 'Distribution name (os-release NAME).';
+COMMENT ON COLUMN host_inventory.os_version IS 'Distribution version (os-release VERSION_ID).';
+COMMENT ON COLUMN host_inventory.kernel_release IS 'Kernel release (uname -r).';
+COMMENT ON COLUMN host_inventory.kernel_version IS 'Kernel build string (uname -v).';
+COMMENT ON COLUMN host_inventory.arch IS 'CPU architecture the agent runs on.';
+COMMENT ON COLUMN host_inventory.cpu_model IS 'CPU model name.';
+COMMENT ON COLUMN host_inventory.cpu_cores IS 'Logical CPUs (0 = not reported).';
+COMMENT ON COLUMN host_inventory.memory_total_bytes IS 'Total RAM in bytes (0 = not reported).';
+COMMENT ON COLUMN host_inventory.security_tools IS 'Installed security tooling as [{name, kind}].';
+COMMENT ON COLUMN host_inventory.disk_encryption IS 'encrypted (root on dm-crypt), partial, none or unknown.';
+COMMENT ON COLUMN host_inventory.encrypted_volumes IS 'dm-crypt volumes found on the host.';
+COMMENT ON COLUMN host_inventory.source_event_id IS 'Envelope event_id of the report (raw_events lineage).';
+COMMENT ON COLUMN host_inventory.collected_at IS 'Agent-side time of the report; older reports never replace newer ones.';
+COMMENT ON COLUMN host_inventory.received_at IS 'When ingest stored the report.';
+
+CREATE INDEX IF NOT EXISTS idx_host_inventory_os ON host_inventory (os_name, os_version);
+CREATE INDEX IF NOT EXISTS idx_host_inventory_kernel ON host_inventory (kernel_release);
+CREATE INDEX IF NOT EXISTS idx_host_inventory_disk_encryption ON host_inventory (disk_encryption);
+
+-- coverage_reports: sensor coverage analysis runs (blind hosts/subnets, silent agents)
+CREATE TABLE IF NOT EXISTS coverage_reports (
+  coverage_report_id     uuid PRIMARY KEY,
+  generated_at           timestamptz NOT NULL,
+  window_start           timestamptz NOT NULL,
+  active_hosts           integer NOT NULL,
+  agent_hosts            integer NOT NULL,
+  probe_only_hosts       integer NOT NULL,
+  blind_hosts            integer NOT NULL,
+  blind_subnets          integer NOT NULL,
+  silent_agents          integer NOT NULL,
+  coverage_pct           double precision NOT NULL,
+  report                 jsonb NOT NULL,
+  CONSTRAINT coverage_reports_counts_chk CHECK (
+    active_hosts >= 0 AND agent_hosts >= 0 AND probe_only_hosts >= 0 AND blind_hosts >= 0
+    AND blind_subnets >= 0 AND silent_agents >= 0
+  ),
+  CONSTRAINT coverage_reports_pct_chk CHECK (coverage_pct >= 0 AND coverage_pct <= 100),
+  CONSTRAINT coverage_reports_window_chk CHECK (window_start <= generated_at)
+);
+
+COMMENT ON TABLE coverage_reports IS
+'Purpose: Sensor coverage - active internal hosts and subnets without agent or probe visibility, silent agents and agents without host inventory.\n'
+'Writing module(s): Core Engine coverage report (ransomeye_coverage_report, daily timer).\n'
+'Reading module(s): Dashboards (latest row as the coverage metric), reporting.\n'
+'Retention expectation: long.';
+
+COMMENT ON COLUMN coverage_reports.coverage_report_id IS 'Report run identifier.';
+COMMENT ON COLUMN coverage_reports.generated_at IS 'When the analysis ran (end of the window).';
+COMMENT ON COLUMN coverage_reports.window_start IS 'Start of the activity window analyzed.';
+COMMENT ON COLUMN coverage_reports.active_hosts IS 'Internal addresses seen by any sensor in the window.';
+COMMENT ON COLUMN coverage_reports.agent_hosts IS 'Active hosts reported as local address by an agent.';
+COMMENT ON COLUMN coverage_reports.probe_only_hosts IS 'Active hosts visible only to a DPI probe.';
+COMMENT ON COLUMN coverage_reports.blind_hosts IS 'Active hosts seen only as peers of agents (no agent or probe visibility).';
+COMMENT ON COLUMN coverage_reports.blind_subnets IS 'Subnets in which no active host is visible.';
+COMMENT ON COLUMN coverage_reports.silent_agents IS 'Active enrolled agents without telemetry in the window.';
+COMMENT ON COLUMN coverage_reports.coverage_pct IS 'Share of active hosts visible to an agent or probe (dashboard metric).';
+COMMENT ON COLUMN coverage_reports.report IS 'Full report: per-subnet coverage, blind addresses, silent agents, agents without inventory.';
+
+CREATE INDEX IF NOT EXISTS idx_coverage_reports_generated ON coverage_reports (generated_at);
+
+-- deception_effectiveness: per decoy asset interaction, source and detection counts per analytics run
+CREATE TABLE IF NOT EXISTS deception_effectiveness (
+  deception_run_id       uuid NOT NULL,
+  generated_at           timestamptz NOT NULL,
+  window_start           timestamptz NOT NULL,
+  asset_kind             text NOT NULL,
+  asset_key              text NOT NULL,
+  asset_name             text NULL,
+  deployed_at            timestamptz NULL,
+  interactions           bigint NOT NULL,
+  first_touch_at         timestamptz NULL,
+  last_touch_at          timestamptz NULL,
+  time_to_first_touch_secs bigint NULL,
+  unique_agents          integer NOT NULL,
+  unique_users           integer NOT NULL,
+  unique_processes       integer NOT NULL,
+  unique_source_ips      integer NOT NULL,
+  detections             integer NOT NULL,
+  incidents              integer NOT NULL,
+  PRIMARY KEY (deception_run_id, asset_kind, asset_key),
+  CONSTRAINT deception_effectiveness_kind_chk CHECK (asset_kind IN ('honeyfile', 'honeycredential', 'fake_service')),
+  CONSTRAINT deception_effectiveness_counts_chk CHECK (
+    interactions >= 0 AND unique_agents >= 0 AND unique_users >= 0 AND unique_processes >= 0
+    AND unique_source_ips >= 0 AND detections >= 0 AND incidents >= 0
+  ),
+  CONSTRAINT deception_effectiveness_ttft_chk CHECK (time_to_first_touch_secs IS NULL OR time_to_first_touch_secs >= 0),
+  CONSTRAINT deception_effectiveness_window_chk CHECK (window_start <= generated_at)
+);
+
+COMMENT ON TABLE deception_effectiveness IS
+'Purpose: Deception effectiveness - per decoy asset interactions, time-to-first-touch, unique sources and the detections/incidents that followed, one row per asset per analytics run.\n'
+'Writing module(s): Core Engine deception analytics (ransomeye_deception_analytics, daily timer).\n'
+'Reading module(s): Reporting (deception report effectiveness section), dashboards, deception analytics (earlier first touches).\n'
+'Retention expectation: long.';
+
+COMMENT ON COLUMN deception_effectiveness.deception_run_id IS 'Analytics run identifier (shared by the rows of one run).';
+COMMENT ON COLUMN deception_effectiveness.generated_at IS 'When the analysis ran (end of the window).';
+COMMENT ON COLUMN deception_effectiveness.window_start IS 'Start of the analysis window.';
+COMMENT ON COLUMN deception_effectiveness.asset_kind IS 'honeyfile (file_path), honeycredential (auth_user) or fake_service (protocol).';
+COMMENT ON COLUMN deception_effectiveness.asset_key IS 'Telemetry value naming the asset (path, honeycredential user or service).';
+COMMENT ON COLUMN deception_effectiveness.asset_name IS 'Name from the asset manifest (optional).';
+COMMENT ON COLUMN deception_effectiveness.deployed_at IS 'Deployment time from the asset manifest (NULL when the asset is not listed).';
+COMMENT ON COLUMN deception_effectiveness.interactions IS 'Deception telemetry rows touching the asset in the window (0 for untouched manifest assets).';
+COMMENT ON COLUMN deception_effectiveness.first_touch_at IS 'Earliest touch seen by this or an earlier run.';
+COMMENT ON COLUMN deception_effectiveness.last_touch_at IS 'Latest touch in the window.';
+COMMENT ON COLUMN deception_effectiveness.time_to_first_touch_secs IS 'first_touch_at minus deployed_at (NULL when unknown).';
+COMMENT ON COLUMN deception_effectiveness.unique_agents IS 'Distinct agents (hosts) that touched the asset in the window.';
+COMMENT ON COLUMN deception_effectiveness.unique_users IS 'Distinct users (username, else uid) that touched the asset.';
+COMMENT ON COLUMN deception_effectiveness.unique_processes IS 'Distinct process names that touched the asset.';
+COMMENT ON COLUMN deception_effectiveness.unique_source_ips IS 'Distinct remote addresses of the touches.';
+COMMENT ON COLUMN deception_effectiveness.detections IS 'Alerting detections on a touching agent within the attribution window after a touch.';
+COMMENT ON COLUMN deception_effectiveness.incidents IS 'Distinct incidents (artifacts.incident_id) named by those detections.';
+
+CREATE INDEX IF NOT EXISTS idx_deception_effectiveness_generated ON deception_effectiveness (generated_at);
+CREATE INDEX IF NOT EXISTS idx_deception_effectiveness_asset ON deception_effectiveness (asset_kind, asset_key);
+
+-- agent_config_versions: immutable agent config documents offered through rollouts
+CREATE TABLE IF NOT EXISTS agent_config_versions (
+  config_version_id      uuid PRIMARY KEY,
+  agent_type             event_source_type NOT NULL,
+  config                 jsonb NOT NULL,
+  config_sha256          bytea NOT NULL,
+  description            text NULL,
+  created_by             text NOT NULL,
+  created_at             timestamptz NOT NULL DEFAULT now(),
+  CONSTRAINT agent_config_versions_sha256_len_chk CHECK (octet_length(config_sha256) = 32)
+);
+
+COMMENT ON TABLE agent_config_versions IS
+'Purpose: Agent config versions, one row per document submitted for rollout.\n'
+'Writing module(s): Orchestrator config rollout CLI.\n'
+'Reading module(s): Orchestrator config rollout controller, config delivery.\n'
+'Retention expectation: long.';
+
+COMMENT ON COLUMN agent_config_versions.config_version_id IS 'Primary key.';
+COMMENT ON COLUMN agent_config_versions.agent_type IS 'Agent type the config applies to.';
+COMMENT ON COLUMN agent_config_versions.config IS 'Config document as delivered to agents.';
+COMMENT ON COLUMN agent_config_versions.config_sha256 IS 'SHA-256 of the serialized config document.';
+COMMENT ON COLUMN agent_config_versions.description IS 'Operator note (optional).';
+COMMENT ON COLUMN agent_config_versions.created_by IS 'Operator who submitted the version.';
+COMMENT ON COLUMN agent_config_versions.created_at IS 'Submission timestamp.';
+
+-- agent_config_rollouts: ring-by-ring rollout of a config version to one agent type
+CREATE TABLE IF NOT EXISTS agent_config_rollouts (
+  rollout_id             uuid PRIMARY KEY,
+  config_version_id      uuid NOT NULL REFERENCES agent_config_versions(config_version_id) ON UPDATE RESTRICT ON DELETE RESTRICT,
+  agent_type             event_source_type NOT NULL,
+  rings                  smallint[] NOT NULL,
+  current_ring           smallint NOT NULL DEFAULT 0,
+  soak_secs              bigint NOT NULL,
+  status                 text NOT NULL DEFAULT 'active',
+  started_by             text NOT NULL,
+  started_at             timestamptz NOT NULL DEFAULT now(),
+  ring_started_at        timestamptz NOT NULL DEFAULT now(),
+  finished_at            timestamptz NULL,
+  rollback_reason        text NULL,
+  CONSTRAINT agent_config_rollouts_status_chk CHECK (status IN ('active', 'completed', 'rolled_back')),
+  CONSTRAINT agent_config_rollouts_rings_chk CHECK (cardinality(rings) > 0 AND rings[cardinality(rings)] = 100),
+  CONSTRAINT agent_config_rollouts_current_ring_chk CHECK (current_ring >= 0 AND current_ring < cardinality(rings)),
+  CONSTRAINT agent_config_rollouts_soak_chk CHECK (soak_secs > 0),
+  CONSTRAINT agent_config_rollouts_finished_chk CHECK ((status = 'active') = (finished_at IS NULL)),
+  CONSTRAINT agent_config_rollouts_rollback_chk CHECK ((status = 'rolled_back') = (rollback_reason IS NOT NULL))
+);
+
+COMMENT ON TABLE agent_config_rollouts IS
+'Purpose: Config rollouts through fleet-percentage rings with health soak and automatic rollback.\n'
+'Writing module(s): Orchestrator config rollout CLI (start, abort), orchestrator config rollout controller (promote, complete, roll back).\n'
+'Reading module(s): Orchestrator config rollout controller and CLI, UI.\n'
+'Retention expectation: long.';
+
+COMMENT ON COLUMN agent_config_rollouts.rollout_id IS 'Primary key; seeds the per-agent ring bucket.';
+COMMENT ON COLUMN agent_config_rollouts.config_version_id IS 'FK to agent_config_versions.config_version_id.';
+COMMENT ON COLUMN agent_config_rollouts.agent_type IS 'Agent type being rolled out to.';
+COMMENT ON COLUMN agent_config_rollouts.rings IS 'Ring fleet percentages, strictly increasing and ending at 100.';
+COMMENT ON COLUMN agent_config_rollouts.current_ring IS 'Index into rings of the ring being soaked.';
+COMMENT ON COLUMN agent_config_rollouts.soak_secs IS 'Healthy soak required before promoting a ring.';
+COMMENT ON COLUMN agent_config_rollouts.status IS 'active, completed, or rolled_back (automatic or operator abort).';
+COMMENT ON COLUMN agent_config_rollouts.started_by IS 'Operator who started the rollout.';
+COMMENT ON COLUMN agent_config_rollouts.started_at IS 'Rollout start timestamp.';
+COMMENT ON COLUMN agent_config_rollouts.ring_started_at IS 'When the current ring started; the health window starts here.';
+COMMENT ON COLUMN agent_config_rollouts.finished_at IS 'When the rollout completed or was rolled back.';
+COMMENT ON COLUMN agent_config_rollouts.rollback_reason IS 'Regression or abort reason of a rolled back rollout.';
+
+CREATE UNIQUE INDEX IF NOT EXISTS agent_config_rollouts_active_uniq_idx ON agent_config_rollouts (agent_type) WHERE status = 'active';
+
+-- agent_config_assignments: desired config version per agent
+CREATE TABLE IF NOT EXISTS agent_config_assignments (
+  agent_id               uuid PRIMARY KEY REFERENCES agents(agent_id) ON UPDATE RESTRICT ON DELETE RESTRICT,
+  config_version_id      uuid NOT NULL REFERENCES agent_config_versions(config_version_id) ON UPDATE RESTRICT ON DELETE RESTRICT,
+  previous_config_version_id uuid NULL REFERENCES agent_config_versions(config_version_id) ON UPDATE RESTRICT ON DELETE RESTRICT,
+  rollout_id             uuid NULL REFERENCES agent_config_rollouts(rollout_id) ON UPDATE RESTRICT ON DELETE RESTRICT,
+  ring                   smallint NULL,
+  assigned_at            timestamptz NOT NULL DEFAULT now()
+);
+
+COMMENT ON TABLE agent_config_assignments IS
+'Purpose: Desired config version of each managed agent (no row: the agent keeps its local config).\n'
+'Writing module(s): Orchestrator config rollout controller.\n'
+'Reading module(s): Config delivery, orchestrator config rollout controller and CLI.\n'
+'Retention expectation: long.';
+
+COMMENT ON COLUMN agent_config_assignments.agent_id IS 'FK to agents.agent_id.';
+COMMENT ON COLUMN agent_config_assignments.config_version_id IS 'Config version the agent should run.';
+COMMENT ON COLUMN agent_config_assignments.previous_config_version_id IS 'Version replaced by the rollout; restored on rollback.';
+COMMENT ON COLUMN agent_config_assignments.rollout_id IS 'Rollout that assigned the version (canary cohort membership).';
+COMMENT ON COLUMN agent_config_assignments.ring IS 'Ring index at which the agent was assigned.';
+COMMENT ON COLUMN agent_config_assignments.assigned_at IS 'Assignment timestamp.';
+
+CREATE INDEX IF NOT EXISTS idx_agent_config_assignments_rollout ON agent_config_assignments (rollout_id) WHERE rollout_id IS NOT NULL;
+
+-- legal_holds: incidents and entities exempted from retention while a hold is active
+CREATE TABLE IF NOT EXISTS legal_holds (
+  hold_id                uuid PRIMARY KEY,
+  subject_type           text NOT NULL,
+  subject_id             uuid NOT NULL,
+  reason                 text NOT NULL,
+  placed_by              text NOT NULL,
+  placed_at              timestamptz NOT NULL DEFAULT now(),
+  released_by            text NULL,
+  release_reason         text NULL,
+  released_at            timestamptz NULL,
+  CONSTRAINT legal_holds_subject_type_chk CHECK (subject_type IN ('incident', 'entity')),
+  CONSTRAINT legal_holds_reason_chk CHECK (length(btrim(reason)) > 0),
+  CONSTRAINT legal_holds_release_chk CHECK ((released_at IS NULL) = (released_by IS NULL))
+);
+
+COMMENT ON TABLE legal_holds IS
+'Purpose: Legal holds on incidents and entities; rows linked to an active hold are never purged by retention.\n'
+'Writing module(s): Core Engine ingestion (admin API).\n'
+'Reading module(s): Orchestrator retention enforcer, ingestion admin API, reporting retention (exported hold list).\n'
+'Retention expectation: long (released holds are kept as history).';
+
+COMMENT ON COLUMN legal_holds.hold_id IS 'Primary key.';
+COMMENT ON COLUMN legal_holds.subject_type IS 'incident (rows with a matching incident_id) or entity (rows with a matching *entity_id).';
+COMMENT ON COLUMN legal_holds.subject_id IS 'Held incident or entity UUID.';
+COMMENT ON COLUMN legal_holds.reason IS 'Why the hold was placed (matter / case reference).';
+COMMENT ON COLUMN legal_holds.placed_by IS 'Operator who placed the hold.';
+COMMENT ON COLUMN legal_holds.placed_at IS 'When the hold was placed.';
+COMMENT ON COLUMN legal_holds.released_by IS 'Operator who released the hold (NULL while active).';
+COMMENT ON COLUMN legal_holds.release_reason IS 'Why the hold was released.';
+COMMENT ON COLUMN legal_holds.released_at IS 'When the hold was released (NULL while active).';
+
+CREATE UNIQUE INDEX IF NOT EXISTS legal_holds_active_subject_uniq_idx ON legal_holds (subject_type, subject_id) WHERE released_at IS NULL;
+
+-- annotations: analyst notes on incidents, detections and timeline entries (text lives in annotation_revisions)
+CREATE TABLE IF NOT EXISTS annotations (
+  annotation_id          uuid PRIMARY KEY,
+  subject_type           text NOT NULL,
+  subject_id             text NOT NULL,
+  created_by             text NOT NULL,
+  created_at             timestamptz NOT NULL DEFAULT now(),
+  CONSTRAINT annotations_subject_type_chk CHECK (subject_type IN ('incident', 'detection', 'timeline_entry')),
+  CONSTRAINT annotations_subject_id_chk CHECK (length(btrim(subject_id)) > 0),
+  CONSTRAINT annotations_created_by_chk CHECK (length(btrim(created_by)) > 0)
+);
+
+COMMENT ON TABLE annotations IS
+'Purpose: Investigation notes attached to incidents, detections or forensic timeline entries; one row per note, its text and edits in annotation_revisions. Append-only.\n'
+'Writing module(s): Core Engine ingestion (admin API).\n'
+'Reading module(s): Ingestion admin API, reporting (exported annotations in forensic reports), UI.\n'
+'Retention expectation: long (notes are part of the investigation record).';
+
+COMMENT ON COLUMN annotations.annotation_id IS 'Primary key.';
+COMMENT ON COLUMN annotations.subject_type IS 'incident, detection (detection_results.detection_id) or timeline_entry (forensic timeline evidence_id / audit record_id).';
+COMMENT ON COLUMN annotations.subject_id IS 'Annotated subject: lowercase UUID for incidents and detections, opaque id for timeline entries.';
+COMMENT ON COLUMN annotations.created_by IS 'Analyst who wrote the note (author of revision 1).';
+COMMENT ON COLUMN annotations.created_at IS 'When the note was added.';
+
+CREATE INDEX IF NOT EXISTS idx_annotations_subject ON annotations (subject_type, subject_id);
+
+DROP TRIGGER IF EXISTS trg_annotations_no_update ON annotations;
+CREATE TRIGGER trg_annotations_no_update
+BEFORE UPDATE OR DELETE ON annotations
+FOR EACH ROW EXECUTE FUNCTION prevent_update_delete();
+
+-- incident_closures: analyst dispositions of incidents (one close per incident)
+CREATE TABLE IF NOT EXISTS incident_closures (
+  incident_id            uuid PRIMARY KEY,
+  disposition            text NOT NULL,
+  closed_by              text NOT NULL,
+  reason                 text NOT NULL,
+  closed_at              timestamptz NOT NULL DEFAULT now(),
+  CONSTRAINT incident_closures_disposition_chk CHECK (disposition IN ('true_positive', 'false_positive')),
+  CONSTRAINT incident_closures_closed_by_chk CHECK (length(btrim(closed_by)) > 0),
+  CONSTRAINT incident_closures_reason_chk CHECK (length(btrim(reason)) > 0)
+);
+
+COMMENT ON TABLE incident_closures IS
+'Purpose: Analyst disposition of each closed incident (the detections naming it in artifacts.incident_id); feeds the true/false positive counts of rule_metrics. Append-only.\n'
+'Writing module(s): Core Engine ingestion (admin API).\n'
+'Reading module(s): Core Engine ingestion (rule metrics refresh, admin API), UI.\n'
+'Retention expectation: long (dispositions are part of the investigation record).';
+
+COMMENT ON COLUMN incident_closures.incident_id IS 'Closed incident (artifacts.incident_id of its detections).';
+COMMENT ON COLUMN incident_closures.disposition IS 'true_positive or false_positive.';
+COMMENT ON COLUMN incident_closures.closed_by IS 'Analyst who closed the incident.';
+COMMENT ON COLUMN incident_closures.reason IS 'Why the incident was closed with this disposition.';
+COMMENT ON COLUMN incident_closures.closed_at IS 'When the incident was closed.';
+
+DROP TRIGGER IF EXISTS trg_incident_closures_no_update ON incident_closures;
+CREATE TRIGGER trg_incident_closures_no_update
+BEFORE UPDATE OR DELETE ON incident_closures
+FOR EACH ROW EXECUTE FUNCTION prevent_update_delete();
+
+-- rule_metrics: per detection rule firing counts, suppression, dispositions and time-to-triage
+CREATE TABLE IF NOT EXISTS rule_metrics (
+  detection_engine       text NOT NULL,
+  detection_name         text NOT NULL,
+  first_fired_at         timestamptz NOT NULL,
+  last_fired_at          timestamptz NOT NULL,
+  window_start           timestamptz NOT NULL,
+  firings                bigint NOT NULL,
+  suppressed             bigint NOT NULL,
+  true_positives         bigint NOT NULL,
+  false_positives        bigint NOT NULL,
+  triaged                bigint NOT NULL,
+  median_triage_secs     double precision NULL,
+  refreshed_at           timestamptz NOT NULL DEFAULT now(),
+  PRIMARY KEY (detection_engine, detection_name),
+  CONSTRAINT rule_metrics_fired_chk CHECK (first_fired_at <= last_fired_at),
+  CONSTRAINT rule_metrics_counts_chk CHECK (
+    suppressed >= 0 AND suppressed <= firings
+    AND true_positives >= 0 AND false_positives >= 0 AND true_positives + false_positives <= firings
+    AND triaged >= 0 AND triaged <= firings
+  ),
+  CONSTRAINT rule_metrics_triage_chk CHECK (median_triage_secs IS NULL OR median_triage_secs >= 0)
+);
+
+COMMENT ON TABLE rule_metrics IS
+'Purpose: One row per detection rule (detection_engine, detection_name) with its counts over the trailing window; rules that stop firing keep their row with zero counts. Source of the rule-tuning report (noisy and dead rules).\n'
+'Writing module(s): Core Engine ingestion (periodic rule metrics refresh).\n'
+'Reading module(s): Core Engine ingestion (admin API), UI.\n'
+'Retention expectation: long (one row per rule, overwritten on refresh).';
+
+COMMENT ON COLUMN rule_metrics.detection_engine IS 'Engine of the rule (detection_results.detection_engine).';
+COMMENT ON COLUMN rule_metrics.detection_name IS 'Rule name (detection_results.detection_name).';
+COMMENT ON COLUMN rule_metrics.first_fired_at IS 'Earliest detection seen by any refresh.';
+COMMENT ON COLUMN rule_metrics.last_fired_at IS 'Latest detection seen by any refresh; kept after the window has moved past it.';
+COMMENT ON COLUMN rule_metrics.window_start IS 'Start of the window the counts cover (up to refreshed_at).';
+COMMENT ON COLUMN rule_metrics.firings IS 'Detections created in the window.';
+COMMENT ON COLUMN rule_metrics.suppressed IS 'Of firings, detections stored under a suppression rule (suppression_id set).';
+COMMENT ON COLUMN rule_metrics.true_positives IS 'Of firings, detections whose incident was later closed as true_positive.';
+COMMENT ON COLUMN rule_metrics.false_positives IS 'Of firings, detections whose incident was later closed as false_positive.';
+COMMENT ON COLUMN rule_metrics.triaged IS 'Of firings, detections an analyst acted on: incident closed, or detection or incident annotated, after the detection.';
+COMMENT ON COLUMN rule_metrics.median_triage_secs IS 'Median seconds from detection to its first analyst action (NULL when none was triaged).';
+COMMENT ON COLUMN rule_metrics.refreshed_at IS 'When the row was last refreshed.';
+
+-- annotation_revisions: every version of every note; an edit appends the next revision
+CREATE TABLE IF NOT EXISTS annotation_revisions (
+  annotation_id          uuid NOT NULL REFERENCES annotations(annotation_id) ON UPDATE RESTRICT ON DELETE RESTRICT,
+  revision               integer NOT NULL,
+  body                   text NOT NULL,
+  author                 text NOT NULL,
+  edited_at              timestamptz NOT NULL DEFAULT now(),
+  edit_reason            text NULL,
+  PRIMARY KEY (annotation_id, revision),
+  CONSTRAINT annotation_revisions_revision_chk CHECK (revision >= 1),
+  CONSTRAINT annotation_revisions_body_chk CHECK (length(btrim(body)) > 0 AND octet_length(body) <= 16384),
+  CONSTRAINT annotation_revisions_author_chk CHECK (length(btrim(author)) > 0),
+  CONSTRAINT annotation_revisions_reason_chk CHECK ((revision = 1) = (edit_reason IS NULL))
+);
+
+COMMENT ON TABLE annotation_revisions IS
+'Purpose: Immutable edit history of annotations; revision 1 is the original text, the highest revision the current one. Append-only.\n'
+'Writing module(s): Core Engine ingestion (admin API).\n'
+'Reading module(s): Ingestion admin API, reporting (exported annotations in forensic reports), UI.\n'
+'Retention expectation: long (kept with annotations).';
+
+COMMENT ON COLUMN annotation_revisions.annotation_id IS 'Annotated note (FK annotations).';
+COMMENT ON COLUMN annotation_revisions.revision IS 'Revision number, 1 upwards without gaps; (annotation_id, revision) is unique so concurrent edits of one revision conflict.';
+COMMENT ON COLUMN annotation_revisions.body IS 'Note text of this revision (at most 16 KiB).';
+COMMENT ON COLUMN annotation_revisions.author IS 'Analyst who wrote this revision.';
+COMMENT ON COLUMN annotation_revisions.edited_at IS 'When this revision was written.';
+COMMENT ON COLUMN annotation_revisions.edit_reason IS 'Why the note was edited (NULL for revision 1).';
+
+DROP TRIGGER IF EXISTS trg_annotation_revisions_no_update ON annotation_revisions;
+CREATE TRIGGER trg_annotation_revisions_no_update
+BEFORE UPDATE OR DELETE ON annotation_revisions
+FOR EACH ROW EXECUTE FUNCTION prevent_update_delete();
+
+-- ingest_component_quotas: effective per-component-type ingestion budgets resolved from signed policies
+CREATE TABLE IF NOT EXISTS ingest_component_quotas (
+  component_type         text PRIMARY KEY,
+  events_per_minute      bigint NOT NULL,
+  policy_id              text NOT NULL,
+  policy_version         text NOT NULL,
+  published_at           timestamptz NOT NULL DEFAULT now(),
+  CONSTRAINT ingest_component_quotas_rate_chk CHECK (events_per_minute > 0)
+);
+
+COMMENT ON TABLE ingest_component_quotas IS
+'Purpose: Current ingestion quota per component type as evaluated by the Policy Engine; replaced as a whole on every publish.\n'
+'Writing module(s): Core Orchestrator (after policy engine initialization).\n'
+'Reading module(s): Core Engine ingestion (rate budget enforcement), UI, Validator.\n'
+'Retention expectation: short (current state only).';
+
+COMMENT ON COLUMN ingest_component_quotas.component_type IS 'Primary key. Producer type as labelled by ingest (linux_agent, dpi_probe, ...).';
+COMMENT ON COLUMN ingest_component_quotas.events_per_minute IS 'Events accepted per component instance per one-minute window.';
+COMMENT ON COLUMN ingest_component_quotas.policy_id IS 'Policy that supplied the effective quota.';
+COMMENT ON COLUMN ingest_component_quotas.policy_version IS 'Version of that policy.';
+COMMENT ON COLUMN ingest_component_quotas.published_at IS 'When the orchestrator published this quota.';
+
+-- feature_flags: runtime switches for risky capabilities, global values and per-tenant overrides
+CREATE TABLE IF NOT EXISTS feature_flags (
+  flag_key               text NOT NULL,
+  tenant_id              text NULL,
+  enabled                boolean NOT NULL,
+  reason                 text NOT NULL,
+  updated_by             text NOT NULL,
+  updated_at             timestamptz NOT NULL DEFAULT now(),
+  CONSTRAINT feature_flags_key_chk CHECK (flag_key ~ '^[a-z][a-z0-9_]{0,63}$'),
+  CONSTRAINT feature_flags_tenant_chk CHECK (tenant_id IS NULL OR length(btrim(tenant_id)) > 0),
+  CONSTRAINT feature_flags_reason_chk CHECK (length(btrim(reason)) > 0),
+  CONSTRAINT feature_flags_updated_by_chk CHECK (length(btrim(updated_by)) > 0)
+);
+
+COMMENT ON TABLE feature_flags IS
+'Purpose: Runtime feature flags; a tenant row overrides the global row (tenant_id NULL), which overrides the flag default built into the service.\n'
+'Writing module(s): Core Engine ingestion (admin API; changes audited in immutable_audit_log).\n'
+'Reading module(s): Core Engine ingestion (cached, refreshed periodically), UI.\n'
+'Retention expectation: short (current state only; history in the audit log).';
+
+COMMENT ON COLUMN feature_flags.flag_key IS 'Flag name (memory_acquisition, pcap_capture, sandbox_detonation, yara_scanning, ...).';
+COMMENT ON COLUMN feature_flags.tenant_id IS 'Tenant the override applies to (agents.tenant_id); NULL for the global value.';
+COMMENT ON COLUMN feature_flags.enabled IS 'Whether the capability is switched on at this level.';
+COMMENT ON COLUMN feature_flags.reason IS 'Why the flag was last set (ticket / change reference).';
+COMMENT ON COLUMN feature_flags.updated_by IS 'Operator who last set the flag.';
+COMMENT ON COLUMN feature_flags.updated_at IS 'When the flag was last set.';
+
+-- One global row and one row per tenant for each flag
+CREATE UNIQUE INDEX IF NOT EXISTS idx_feature_flags_scope ON feature_flags (flag_key, (COALESCE(tenant_id, '')));
+
+-- ingest_replay_state: last accepted sequence per producer, shared by load-balanced ingest instances
+CREATE TABLE IF NOT EXISTS ingest_replay_state (
+  scope                  text NOT NULL,
+  producer_id            text NOT NULL,
+  last_sequence          bigint NOT NULL,
+  last_observed_at       timestamptz NOT NULL,
+  updated_at             timestamptz NOT NULL DEFAULT now(),
+  PRIMARY KEY (scope, producer_id)
+);
+
+COMMENT ON TABLE ingest_replay_state IS
+'Purpose: Per-producer sequence and timestamp high-water marks for replay protection when RANSOMEYE_INGEST_SHARED_STATE=postgres; only ever moves forward.\n'
+'Writing module(s): Core Engine ingestion (every instance, one upsert per checked event).\n'
+'Reading module(s): Core Engine ingestion.\n'
+'Retention expectation: medium (one row per producer and scope; kept while the producer exists).';
+
+COMMENT ON COLUMN ingest_replay_state.scope IS 'Check that owns the row (auth, ordering); each scope tracks the same producers independently.';
+COMMENT ON COLUMN ingest_replay_state.producer_id IS 'Producer (agent / component) identity the sequence belongs to.';
+COMMENT ON COLUMN ingest_replay_state.last_sequence IS 'Highest sequence number accepted from the producer.';
+COMMENT ON COLUMN ingest_replay_state.last_observed_at IS 'Producer timestamp of the event that set last_sequence.';
+COMMENT ON COLUMN ingest_replay_state.updated_at IS 'When an instance last advanced this row.';
+
+-- ingest_replay_nonces: nonces seen per producer within the replay window
+CREATE TABLE IF NOT EXISTS ingest_replay_nonces (
+  scope                  text NOT NULL,
+  producer_id            text NOT NULL,
+  nonce                  text NOT NULL,
+  observed_at            timestamptz NOT NULL,
+  PRIMARY KEY (scope, producer_id, nonce)
+);
+
+CREATE INDEX IF NOT EXISTS idx_ingest_replay_nonces_observed_at ON ingest_replay_nonces (observed_at);
+
+COMMENT ON TABLE ingest_replay_nonces IS
+'Purpose: Nonces accepted per producer so a replayed event is rejected by whichever ingest instance receives it.\n'
+'Writing module(s): Core Engine ingestion (insert on accept, periodic purge).\n'
+'Reading module(s): Core Engine ingestion.\n'
+'Retention expectation: short (24 hours, purged by ingest).';
+
+COMMENT ON COLUMN ingest_replay_nonces.scope IS 'Check that claimed the nonce (auth, ordering).';
+COMMENT ON COLUMN ingest_replay_nonces.producer_id IS 'Producer the nonce belongs to.';
+COMMENT ON COLUMN ingest_replay_nonces.nonce IS 'Nonce carried by the accepted event.';
+COMMENT ON COLUMN ingest_replay_nonces.observed_at IS 'Producer timestamp of the event; drives the purge.';
+
+-- ingest_rate_windows: current per-component budget window, shared by load-balanced ingest instances
+CREATE TABLE IF NOT EXISTS ingest_rate_windows (
+  component_type         text NOT NULL,
+  component_id           text NOT NULL,
+  window_start           timestamptz NOT NULL,
+  event_count            bigint NOT NULL,
+  PRIMARY KEY (component_type, component_id)
+);
+
+COMMENT ON TABLE ingest_rate_windows IS
+'Purpose: Event count of the current one-minute budget window per component instance when RANSOMEYE_INGEST_SHARED_STATE=postgres (quotas in ingest_component_quotas).\n'
+'Writing module(s): Core Engine ingestion (one upsert per budgeted event, periodic purge).\n'
+'Reading module(s): Core Engine ingestion.\n'
+'Retention expectation: short (windows idle for an hour are purged).';
+
+COMMENT ON COLUMN ingest_rate_windows.component_type IS 'Producer type the quota applies to.';
+COMMENT ON COLUMN ingest_rate_windows.component_id IS 'Component instance the window counts.';
+COMMENT ON COLUMN ingest_rate_windows.window_start IS 'Database time the current window opened.';
+COMMENT ON COLUMN ingest_rate_windows.event_count IS 'Events counted in the window, including rejected ones.';
+
+-- schema_migrations: authoritative schema applies performed against this database
+CREATE TABLE IF NOT EXISTS schema_migrations (
+  migration_id           uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+  contract_sha256        text NOT NULL,
+  kind                   text NOT NULL,
+  tables_applied         text[] NOT NULL DEFAULT '{}',
+  applied_at             timestamptz NOT NULL DEFAULT now(),
+  CONSTRAINT schema_migrations_kind_chk CHECK (kind IN ('full', 'incremental')),
+  CONSTRAINT schema_migrations_sha256_chk CHECK (contract_sha256 ~ '^[0-9a-f]{64}$')
+);
+
+CREATE INDEX IF NOT EXISTS idx_schema_migrations_applied_at ON schema_migrations (applied_at DESC);
+
+COMMENT ON TABLE schema_migrations IS
+'Purpose: History of authoritative schema applies (first-run full apply and incremental table completion).\n'
+'Writing module(s): Core Orchestrator (schema apply at startup).\n'
+'Reading module(s): Core Engine ingestion (/schema probe), UI, Validator.\n'
+'Retention expectation: long (schema provenance).';
+
+COMMENT ON COLUMN schema_migrations.migration_id IS 'Primary key.';
+COMMENT ON COLUMN schema_migrations.contract_sha256 IS 'SHA-256 (hex) of the authoritative schema file that was applied; matches schema.hash.';
+COMMENT ON COLUMN schema_migrations.kind IS 'full (first run, whole file) or incremental (missing required tables only).';
+COMMENT ON COLUMN schema_migrations.tables_applied IS 'Tables created by an incremental apply; empty for full applies.';
+COMMENT ON COLUMN schema_migrations.applied_at IS 'When the apply committed.';
+
+-- schema_validations: outcome of each startup schema contract validation
+CREATE TABLE IF NOT EXISTS schema_validations (
+  validation_id          uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+  contract_sha256        text NOT NULL,
+  status                 text NOT NULL,
+  detail                 text NULL,
+  validated_at           timestamptz NOT NULL DEFAULT now(),
+  CONSTRAINT schema_validations_status_chk CHECK (status IN ('passed', 'failed')),
+  CONSTRAINT schema_validations_sha256_chk CHECK (contract_sha256 ~ '^[0-9a-f]{64}$')
+);
+
+CREATE INDEX IF NOT EXISTS idx_schema_validations_validated_at ON schema_validations (validated_at DESC);
+
+COMMENT ON TABLE schema_validations IS
+'Purpose: Result of every schema contract validation run by the orchestrator at startup.\n'
+'Writing module(s): Core Orchestrator (schema validation at startup).\n'
+'Reading module(s): Core Engine ingestion (/schema probe), UI, Validator.\n'
+'Retention expectation: medium (latest status is what matters; history aids upgrade forensics).';
+
+COMMENT ON COLUMN schema_validations.validation_id IS 'Primary key.';
+COMMENT ON COLUMN schema_validations.contract_sha256 IS 'SHA-256 (hex) of the authoritative schema file validated against.';
+COMMENT ON COLUMN schema_validations.status IS 'passed or failed.';
+COMMENT ON COLUMN schema_validations.detail IS 'Failure reason (missing tables/columns); NULL when passed.';
+COMMENT ON COLUMN schema_validations.validated_at IS 'When the validation ran.';
+
+-- schema_versions: schema generations applied to this database (rolling-upgrade compatibility matrix)
+CREATE TABLE IF NOT EXISTS schema_versions (
+  schema_version         integer PRIMARY KEY,
+  min_code_version       integer NOT NULL,
+  description            text NOT NULL,
+  applied_at             timestamptz NOT NULL DEFAULT now(),
+  CONSTRAINT schema_versions_version_chk CHECK (schema_version > 0),
+  CONSTRAINT schema_versions_min_code_chk CHECK (min_code_version > 0 AND min_code_version <= schema_version)
+);
+
+INSERT INTO schema_versions (schema_version, min_code_version, description)
+VALUES (1, 1, 'baseline')
+ON CONFLICT (schema_version) DO NOTHING;
+
+COMMENT ON TABLE schema_versions IS
+'Purpose: Schema generation of this database; the highest row is current. Services built for another generation refuse to start when the skew exceeds one version or the generation requires newer code.\n'
+'Writing module(s): Core Orchestrator (baseline row), schema migrations of offline update bundles (one row per generation).\n'
+'Reading module(s): Core Orchestrator and ingestion (startup compatibility check, /schema probe).\n'
+'Retention expectation: long (schema provenance).';
+
+COMMENT ON COLUMN schema_versions.schema_version IS 'Primary key. Schema generation; each migration that changes the schema adds the next one.';
+COMMENT ON COLUMN schema_versions.min_code_version IS 'Oldest code schema version that may still run against this generation (schema_version - 1 unless the change is breaking).';
+COMMENT ON COLUMN schema_versions.description IS 'What the generation changed.';
+COMMENT ON COLUMN schema_versions.applied_at IS 'When the generation was applied.';
+
+-- schema_column_renames: renamed columns dual-written for one release cycle
+CREATE TABLE IF NOT EXISTS schema_column_renames (
+  table_name             text NOT NULL,
+  old_column             text NOT NULL,
+  new_column             text NOT NULL,
+  since_version          integer NOT NULL,
+  renamed_at             timestamptz NOT NULL DEFAULT now(),
+  retired_at             timestamptz NULL,
+  PRIMARY KEY (table_name, old_column),
+  CONSTRAINT schema_column_renames_distinct_chk CHECK (old_column <> new_column),
+  CONSTRAINT schema_column_renames_since_chk CHECK (since_version > 0)
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS schema_column_renames_new_uniq_idx ON schema_column_renames (table_name, new_column);
+
+COMMENT ON TABLE schema_column_renames IS
+'Purpose: Column renames kept compatible during a rolling upgrade: both columns exist and the compat_dual_write() trigger keeps them equal, so code reading or writing either name works. Retired one release cycle later, when the old column is dropped.\n'
+'Writing module(s): Schema migrations via compat_rename_column() / compat_retire_rename() (installed by the Core Orchestrator).\n'
+'Reading module(s): compat_dual_write() trigger, Core Orchestrator and ingestion (startup compatibility check, /schema probe).\n'
+'Retention expectation: long (schema provenance).';
+
+COMMENT ON COLUMN schema_column_renames.table_name IS 'Table (schema ransomeye) holding both columns.';
+COMMENT ON COLUMN schema_column_renames.old_column IS 'Name used by code before since_version; dropped on retirement.';
+COMMENT ON COLUMN schema_column_renames.new_column IS 'Name used by code from since_version on.';
+COMMENT ON COLUMN schema_column_renames.since_version IS 'Schema generation that introduced the new name.';
+COMMENT ON COLUMN schema_column_renames.renamed_at IS 'When the rename was registered.';
+COMMENT ON COLUMN schema_column_renames.retired_at IS 'When the old column was dropped; NULL while both are dual-written.';
 
 -- components: canonical identity for services/modules emitting health and audit events
 CREATE TABLE IF NOT EXISTS components (
@@ -183,6 +1163,7 @@ CREATE TABLE IF NOT EXISTS components (
   instance_id            text NULL,
   build_hash             text NULL,
   version                text NULL,
+  build_metadata         jsonb NULL,
   host_agent_id          uuid NULL REFERENCES agents(agent_id) ON UPDATE RESTRICT ON DELETE SET NULL,
   started_at             timestamptz NULL,
   last_heartbeat_at      timestamptz NULL,
@@ -211,6 +1192,7 @@ COMMENT ON COLUMN components.component_name IS 'Logical component name (e.g., ra
 COMMENT ON COLUMN components.instance_id IS 'Optional instance identifier (e.g., systemd unit instance, container id).';
 COMMENT ON COLUMN components.build_hash IS 'Build hash identifier (e.g., git commit) for provenance.';
 COMMENT ON COLUMN components.version IS 'Semantic/packaging version string.';
+COMMENT ON COLUMN components.build_metadata IS 'Build provenance embedded at build time: package, version, git_commit, cargo_lock_sha256, sbom_sha256 (CycloneDX SBOM via --version --json).';
 COMMENT ON COLUMN components.host_agent_id IS 'If the component runs on a host with an agent record, links to that agent.';
 COMMENT ON COLUMN components.started_at IS 'Component start time (first observed for current runtime session).';
 COMMENT ON COLUMN components.last_heartbeat_at IS 'Most recent heartbeat time observed from this component instance.';
@@ -375,7 +1357,14 @@ CREATE TABLE IF NOT EXISTS linux_agent_telemetry (
   network_src_port       integer NULL,
   network_dst_ip         inet NULL,
   network_dst_port       integer NULL,
+  netns_inode            bigint NULL,
+  host_netns             boolean NULL,
   protocol               text NULL,
+  -- Container context (NULL container_id = host activity)
+  container_id           text NULL,
+  container_image        text NULL,
+  pod_name               text NULL,
+  pod_namespace          text NULL,
   payload                jsonb NULL,
   payload_sha256         bytea NULL,
   correlation_hint       text NULL,
@@ -385,7 +1374,8 @@ CREATE TABLE IF NOT EXISTS linux_agent_telemetry (
   CONSTRAINT linux_agent_ports_chk CHECK (
     (network_src_port IS NULL OR (network_src_port BETWEEN 1 AND 65535)) AND
     (network_dst_port IS NULL OR (network_dst_port BETWEEN 1 AND 65535))
-  )
+  ),
+  CONSTRAINT linux_agent_container_id_chk CHECK (container_id IS NULL OR container_id ~ '^[0-9a-f]{64}$')
 );
 
 COMMENT ON TABLE linux_agent_telemetry IS
@@ -416,7 +1406,13 @@ COMMENT ON COLUMN linux_agent_telemetry.network_src_ip IS 'Source IP for network
 COMMENT ON COLUMN linux_agent_telemetry.network_src_port IS 'Source port for network events (optional).';
 COMMENT ON COLUMN linux_agent_telemetry.network_dst_ip IS 'Destination IP for network events (optional).';
 COMMENT ON COLUMN linux_agent_telemetry.network_dst_port IS 'Destination port for network events (optional).';
+COMMENT ON COLUMN linux_agent_telemetry.netns_inode IS 'Network namespace inode of the socket for network events (optional).';
+COMMENT ON COLUMN linux_agent_telemetry.host_netns IS 'Whether the socket was in the host network namespace; false means container/pod addresses (optional).';
 COMMENT ON COLUMN linux_agent_telemetry.protocol IS 'L4/L7 protocol label if available (optional).';
+COMMENT ON COLUMN linux_agent_telemetry.container_id IS 'Container the process ran in, resolved by the agent from its cgroup (64-hex id). NULL means host activity.';
+COMMENT ON COLUMN linux_agent_telemetry.container_image IS 'Container image from the kubelet pod status or the Docker container config (optional).';
+COMMENT ON COLUMN linux_agent_telemetry.pod_name IS 'Kubernetes pod name from the kubelet pod list (optional; NULL for non-Kubernetes containers).';
+COMMENT ON COLUMN linux_agent_telemetry.pod_namespace IS 'Kubernetes namespace of the pod (optional).';
 COMMENT ON COLUMN linux_agent_telemetry.payload IS 'Optional structured payload for event-specific fields (JSONB justified for heterogeneity).';
 COMMENT ON COLUMN linux_agent_telemetry.payload_sha256 IS 'SHA-256 digest of payload for integrity/deduplication (optional).';
 COMMENT ON COLUMN linux_agent_telemetry.correlation_hint IS 'Optional hint key used by correlator (e.g., session id, trace id).';
@@ -426,6 +1422,9 @@ CREATE INDEX IF NOT EXISTS idx_linux_agent_telemetry_observed_at ON linux_agent_
 CREATE INDEX IF NOT EXISTS idx_linux_agent_telemetry_received_at ON linux_agent_telemetry (received_at);
 CREATE INDEX IF NOT EXISTS idx_linux_agent_telemetry_agent_id ON linux_agent_telemetry (agent_id);
 CREATE INDEX IF NOT EXISTS idx_linux_agent_telemetry_agent_observed_at ON linux_agent_telemetry (agent_id, observed_at DESC);
+CREATE INDEX IF NOT EXISTS idx_linux_agent_telemetry_network_src ON linux_agent_telemetry (network_src_ip, network_src_port, observed_at) WHERE network_src_ip IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_linux_agent_telemetry_container_id ON linux_agent_telemetry (container_id) WHERE container_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_linux_agent_telemetry_pod ON linux_agent_telemetry (pod_namespace, pod_name) WHERE pod_name IS NOT NULL;
 CREATE UNIQUE INDEX IF NOT EXISTS idx_linux_agent_telemetry_source_message_id_uniq
   ON linux_agent_telemetry (source_message_id)
   WHERE source_message_id IS NOT NULL;
@@ -642,7 +1641,7 @@ CREATE TABLE IF NOT EXISTS public.telemetry_events (
 
 COMMENT ON TABLE public.telemetry_events IS
 'Purpose: Unified signed telemetry stream for consumers that query without ransomeye search_path (e.g., Posture Engine).\n'
-'Writing module(s): Populated by DB triggers from linux_agent_telemetry/windows_agent_telemetry/dpi_probe_telemetry.\n'
+'Writing module(s): Populated by DB triggers from linux_agent_telemetry/windows_agent_telemetry/dpi_probe_telemetry; rows whose source telemetry was purged are removed by the retention orphan GC.\n'
 'Reading module(s): Posture Engine (signature verification), UI.\n'
 'Retention expectation: long.';
 
@@ -846,6 +1845,82 @@ COMMENT ON TABLE retention_policies IS
 
 CREATE INDEX IF NOT EXISTS idx_retention_policies_enabled ON retention_policies (retention_enabled);
 
+-- retention_runs: one row per retention enforcer run (live or dry), successful or failed
+CREATE TABLE IF NOT EXISTS retention_runs (
+  run_id                  uuid PRIMARY KEY,
+  mode                    text NOT NULL,
+  status                  text NOT NULL,
+  started_at              timestamptz NOT NULL,
+  ended_at                timestamptz NOT NULL,
+  duration_ms             bigint NOT NULL,
+  tables                  bigint NOT NULL DEFAULT 0,
+  rows_would_purge        bigint NOT NULL DEFAULT 0,
+  rows_deleted            bigint NOT NULL DEFAULT 0,
+  held_rows_skipped       bigint NOT NULL DEFAULT 0,
+  referenced_rows_skipped bigint NOT NULL DEFAULT 0,
+  orphan_rows_deleted     bigint NOT NULL DEFAULT 0,
+  orphan_rows_marked      bigint NOT NULL DEFAULT 0,
+  lock_timeouts           bigint NOT NULL DEFAULT 0,
+  audit_id                uuid NULL,
+  error                   text NULL,
+  table_stats             jsonb NOT NULL DEFAULT '[]'::jsonb,
+  CONSTRAINT retention_runs_mode_chk CHECK (mode IN ('dry_run', 'live')),
+  CONSTRAINT retention_runs_status_chk CHECK (status IN ('succeeded', 'failed')),
+  CONSTRAINT retention_runs_time_chk CHECK (started_at <= ended_at AND duration_ms >= 0),
+  CONSTRAINT retention_runs_counts_chk CHECK (
+    tables >= 0 AND rows_would_purge >= 0 AND rows_deleted >= 0 AND held_rows_skipped >= 0
+    AND referenced_rows_skipped >= 0 AND orphan_rows_deleted >= 0 AND orphan_rows_marked >= 0 AND lock_timeouts >= 0
+  ),
+  CONSTRAINT retention_runs_outcome_chk CHECK (
+    (status = 'succeeded' AND audit_id IS NOT NULL AND error IS NULL)
+    OR (status = 'failed' AND error IS NOT NULL)
+  ),
+  CONSTRAINT retention_runs_table_stats_chk CHECK (jsonb_typeof(table_stats) = 'array')
+);
+
+COMMENT ON TABLE retention_runs IS
+'Purpose: Queryable history of retention enforcer runs: mode, outcome, timing, totals and a per table summary. The full run payload stays in immutable_audit_log (audit_id). Source of the retention schedule health (failed, overdue, never-run alerts).\n'
+'Writing module(s): Retention enforcer (one row per run, on success and on failure).\n'
+'Reading module(s): Core Engine ingestion (admin API), retention enforcer (--runs, --status), UI.\n'
+'Retention expectation: long (one row per run; purge with a retention_policies row if needed).';
+
+COMMENT ON COLUMN retention_runs.mode IS 'dry_run (counts only) or live (rows deleted). Only live runs satisfy the schedule.';
+COMMENT ON COLUMN retention_runs.status IS 'succeeded or failed; a failed run has error set and zero totals.';
+COMMENT ON COLUMN retention_runs.tables IS 'Tables with an enabled retention policy.';
+COMMENT ON COLUMN retention_runs.rows_would_purge IS 'Rows past their cutoff when the run counted them, held and referenced rows included (what a dry run reports).';
+COMMENT ON COLUMN retention_runs.rows_deleted IS 'Rows deleted from retention targets (0 for a dry run).';
+COMMENT ON COLUMN retention_runs.held_rows_skipped IS 'Rows kept for an active legal hold (targets and orphan links).';
+COMMENT ON COLUMN retention_runs.referenced_rows_skipped IS 'Rows kept because a row outside the purge still references them.';
+COMMENT ON COLUMN retention_runs.orphan_rows_deleted IS 'Derived rows deleted by orphan collection.';
+COMMENT ON COLUMN retention_runs.orphan_rows_marked IS 'Derived rows marked source-purged by orphan collection.';
+COMMENT ON COLUMN retention_runs.audit_id IS 'immutable_audit_log entry with the full run payload (NULL for a failed run).';
+COMMENT ON COLUMN retention_runs.error IS 'Why the run failed (truncated to 4096 bytes).';
+COMMENT ON COLUMN retention_runs.table_stats IS 'Per table: table, eligible, rows_would_purge, deleted_rows, batches_executed, held_rows_skipped, referenced_rows_skipped, lock_timeouts (JSONB justified: variable table set).';
+
+CREATE INDEX IF NOT EXISTS idx_retention_runs_started_at ON retention_runs (started_at DESC);
+CREATE INDEX IF NOT EXISTS idx_retention_runs_live_succeeded ON retention_runs (started_at DESC) WHERE mode = 'live' AND status = 'succeeded';
+
+-- db_size_samples: periodic database and table sizes, the history of the capacity forecast
+CREATE TABLE IF NOT EXISTS db_size_samples (
+  sample_id               uuid PRIMARY KEY,
+  sampled_at              timestamptz NOT NULL,
+  database_bytes          bigint NOT NULL,
+  tables                  jsonb NOT NULL DEFAULT '{}'::jsonb,
+  CONSTRAINT db_size_samples_bytes_chk CHECK (database_bytes >= 0),
+  CONSTRAINT db_size_samples_tables_chk CHECK (jsonb_typeof(tables) = 'object')
+);
+
+COMMENT ON TABLE db_size_samples IS
+'Purpose: Database and per table sizes sampled by the orchestrator; growth rates and the days-until-full forecast are fitted on them.\n'
+'Writing module(s): Core Orchestrator (capacity forecast, one row per RANSOMEYE_DB_CAPACITY_SAMPLE_SECS).\n'
+'Reading module(s): Core Orchestrator (capacity forecast in GET /v1/status), UI.\n'
+'Retention expectation: short (the orchestrator deletes samples older than 90 days).';
+
+COMMENT ON COLUMN db_size_samples.database_bytes IS 'pg_database_size of the database.';
+COMMENT ON COLUMN db_size_samples.tables IS 'schema.table -> pg_total_relation_size (heap, indexes, TOAST) for the ransomeye and public schemas (JSONB justified: variable table set).';
+
+CREATE INDEX IF NOT EXISTS idx_db_size_samples_sampled_at ON db_size_samples (sampled_at);
+
 -- ============================================================================
 -- Public-schema tables required by modules that create/query without ransomeye search_path
 -- ============================================================================
@@ -988,12 +2063,18 @@ CREATE TABLE IF NOT EXISTS raw_events (
   payload_json           jsonb NULL,
   payload_bytes          bytea NULL,
   payload_sha256         bytea NOT NULL,
+  payload_storage        text NOT NULL DEFAULT 'full',
+  payload_storage_reason text NULL,
+  residency_region       text NULL,
+  site                   text NULL,
+  zone                   text NULL,
   schema_version         text NULL,
   is_replay              boolean NOT NULL DEFAULT false,
   replay_source          text NULL,
   created_at             timestamptz NOT NULL DEFAULT now(),
   CONSTRAINT raw_events_payload_presence_chk CHECK (payload_json IS NOT NULL OR payload_bytes IS NOT NULL),
-  CONSTRAINT raw_events_payload_sha256_len_chk CHECK (octet_length(payload_sha256) = 32)
+  CONSTRAINT raw_events_payload_sha256_len_chk CHECK (octet_length(payload_sha256) = 32),
+  CONSTRAINT raw_events_payload_storage_chk CHECK (payload_storage IN ('full', 'summary'))
 );
 
 COMMENT ON TABLE raw_events IS
@@ -1015,6 +2096,11 @@ COMMENT ON COLUMN raw_events.trace_id IS 'Distributed trace identifier for cross
 COMMENT ON COLUMN raw_events.payload_json IS 'Raw payload in JSONB (preferred for structured events).';
 COMMENT ON COLUMN raw_events.payload_bytes IS 'Raw payload bytes for non-JSON payloads (optional).';
 COMMENT ON COLUMN raw_events.payload_sha256 IS 'SHA-256 digest of canonical payload representation for integrity/deduplication.';
+COMMENT ON COLUMN raw_events.payload_storage IS 'Ingest payload storage policy decision: full (payload_json is the envelope as received) or summary (payload_json holds hashes and extracted fields only; payload_sha256 still covers the full envelope).';
+COMMENT ON COLUMN raw_events.payload_storage_reason IS 'Policy rule that produced payload_storage (e.g. policy:full, category:detection, event_type:MassWrite, sampled, routine).';
+COMMENT ON COLUMN raw_events.residency_region IS 'Residency classification of the event data: the producing agent''s region (the ingest instance''s region for untagged agents). NULL when residency is not configured and the agent is untagged.';
+COMMENT ON COLUMN raw_events.site IS 'Site of the sending sensor in the signed topology document (RANSOMEYE_TOPOLOGY_PATH). NULL when no topology is configured or the sensor is not placed.';
+COMMENT ON COLUMN raw_events.zone IS 'Zone of the sending sensor in the signed topology document. NULL when no topology is configured or the sensor is not placed.';
 COMMENT ON COLUMN raw_events.schema_version IS 'Optional emitter schema version tag.';
 COMMENT ON COLUMN raw_events.is_replay IS 'True if this raw event was re-ingested from forensic replay.';
 COMMENT ON COLUMN raw_events.replay_source IS 'Replay source identifier (e.g., bundle id, evidence id) if is_replay is true.';
@@ -1025,6 +2111,8 @@ CREATE INDEX IF NOT EXISTS idx_raw_events_observed_at ON raw_events (observed_at
 CREATE INDEX IF NOT EXISTS idx_raw_events_source_agent_id ON raw_events (source_agent_id);
 CREATE INDEX IF NOT EXISTS idx_raw_events_source_component_id ON raw_events (source_component_id);
 CREATE INDEX IF NOT EXISTS idx_raw_events_payload_sha256 ON raw_events (payload_sha256);
+CREATE INDEX IF NOT EXISTS idx_raw_events_zone ON raw_events (zone, observed_at) WHERE zone IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_raw_events_agent_zone ON raw_events (source_agent_id, observed_at) WHERE zone IS NOT NULL;
 
 CREATE TABLE IF NOT EXISTS normalized_events (
   normalized_event_id    uuid PRIMARY KEY DEFAULT gen_random_uuid(),
@@ -1044,12 +2132,14 @@ CREATE TABLE IF NOT EXISTS normalized_events (
   attributes             jsonb NULL,
   deterministic_key      bytea NOT NULL,
   created_at             timestamptz NOT NULL DEFAULT now(),
-  CONSTRAINT normalized_events_det_key_len_chk CHECK (octet_length(deterministic_key) IN (16, 20, 32, 64))
+  normalizer_version     integer NOT NULL DEFAULT 1,
+  CONSTRAINT normalized_events_det_key_len_chk CHECK (octet_length(deterministic_key) IN (16, 20, 32, 64)),
+  CONSTRAINT normalized_events_normalizer_version_chk CHECK (normalizer_version >= 1)
 );
 
 COMMENT ON TABLE normalized_events IS
 'Purpose: Canonical normalized event representation derived from raw_events, enabling deterministic correlation, policy evaluation, and replay.\n'
-'Writing module(s): Normalizer (Core Engine pipeline).\n'
+'Writing module(s): Normalizer (Core Engine pipeline), normalization backfill (cutover of re-normalized rows).\n'
 'Reading module(s): Correlation Engine, Policy Engine, AI/ML, Forensic Engine, UI.\n'
 'Retention expectation: long.';
 
@@ -1070,6 +2160,7 @@ COMMENT ON COLUMN normalized_events.target_entity_id IS 'Optional FK to target e
 COMMENT ON COLUMN normalized_events.attributes IS 'Structured normalized attributes (JSONB justified for variable event facets).';
 COMMENT ON COLUMN normalized_events.deterministic_key IS 'Deterministic correlation key derived from normalized fields (digest) for dedup/correlation.';
 COMMENT ON COLUMN normalized_events.created_at IS 'Row creation timestamp.';
+COMMENT ON COLUMN normalized_events.normalizer_version IS 'NORMALIZER_VERSION of the normalizer that produced the row; raised in place when a backfill is cut over.';
 
 CREATE INDEX IF NOT EXISTS idx_normalized_events_normalized_at ON normalized_events (normalized_at);
 CREATE INDEX IF NOT EXISTS idx_normalized_events_observed_at ON normalized_events (observed_at);
@@ -1079,6 +2170,91 @@ CREATE INDEX IF NOT EXISTS idx_normalized_events_source_component_id ON normaliz
 CREATE INDEX IF NOT EXISTS idx_normalized_events_primary_entity_id ON normalized_events (primary_entity_id);
 CREATE INDEX IF NOT EXISTS idx_normalized_events_det_key ON normalized_events (deterministic_key);
 
+-- normalization_backfills: re-normalization runs of historical raw_events (progress checkpoint and cutover state)
+CREATE TABLE IF NOT EXISTS normalization_backfills (
+  backfill_id            uuid PRIMARY KEY,
+  normalizer_version     integer NOT NULL,
+  range_start            timestamptz NOT NULL,
+  range_end              timestamptz NOT NULL,
+  status                 text NOT NULL DEFAULT 'running',
+  checkpoint_received_at timestamptz NULL,
+  checkpoint_raw_event_id uuid NULL,
+  events_read            bigint NOT NULL DEFAULT 0,
+  events_staged          bigint NOT NULL DEFAULT 0,
+  events_failed          bigint NOT NULL DEFAULT 0,
+  events_skipped         bigint NOT NULL DEFAULT 0,
+  events_cut_over        bigint NOT NULL DEFAULT 0,
+  started_at             timestamptz NOT NULL DEFAULT now(),
+  updated_at             timestamptz NOT NULL DEFAULT now(),
+  staged_at              timestamptz NULL,
+  cut_over_at            timestamptz NULL,
+  CONSTRAINT normalization_backfills_range_chk CHECK (range_start < range_end),
+  CONSTRAINT normalization_backfills_version_chk CHECK (normalizer_version >= 1),
+  CONSTRAINT normalization_backfills_status_chk CHECK (status IN ('running', 'staged', 'cutting_over', 'cut_over')),
+  CONSTRAINT normalization_backfills_checkpoint_chk CHECK ((checkpoint_received_at IS NULL) = (checkpoint_raw_event_id IS NULL)),
+  CONSTRAINT normalization_backfills_run_uniq UNIQUE (normalizer_version, range_start, range_end)
+);
+
+COMMENT ON TABLE normalization_backfills IS
+'Purpose: One row per re-normalization backfill of a received_at range to a normalizer version; the checkpoint makes a rerun of the same range and version resume where it stopped.\n'
+'Writing module(s): Normalization backfill (core/normalization_worker/backfill.py).\n'
+'Reading module(s): Normalization backfill, operators.\n'
+'Retention expectation: long (record of which ranges were re-normalized).';
+
+COMMENT ON COLUMN normalization_backfills.backfill_id IS 'Primary key.';
+COMMENT ON COLUMN normalization_backfills.normalizer_version IS 'Normalizer version the range is re-normalized to.';
+COMMENT ON COLUMN normalization_backfills.range_start IS 'Start of the raw_events.received_at range (inclusive).';
+COMMENT ON COLUMN normalization_backfills.range_end IS 'End of the raw_events.received_at range (exclusive).';
+COMMENT ON COLUMN normalization_backfills.status IS 'running (staging), staged (all rows staged, old version still live), cutting_over, cut_over (new version live).';
+COMMENT ON COLUMN normalization_backfills.checkpoint_received_at IS 'received_at of the last raw event processed; staging resumes after (checkpoint_received_at, checkpoint_raw_event_id).';
+COMMENT ON COLUMN normalization_backfills.checkpoint_raw_event_id IS 'raw_event_id of the last raw event processed.';
+COMMENT ON COLUMN normalization_backfills.events_read IS 'Normalized raw events of an older version read from the range.';
+COMMENT ON COLUMN normalization_backfills.events_staged IS 'Re-normalized rows written to normalized_event_versions.';
+COMMENT ON COLUMN normalization_backfills.events_failed IS 'Raw events the normalizer rejected; their old version stays live.';
+COMMENT ON COLUMN normalization_backfills.events_skipped IS 'Raw events stored as a payload summary (payload_storage = summary) that cannot be re-normalized; their old version stays live.';
+COMMENT ON COLUMN normalization_backfills.events_cut_over IS 'normalized_events rows replaced by their staged version.';
+COMMENT ON COLUMN normalization_backfills.started_at IS 'When the backfill was started.';
+COMMENT ON COLUMN normalization_backfills.updated_at IS 'Last checkpoint.';
+COMMENT ON COLUMN normalization_backfills.staged_at IS 'When staging finished.';
+COMMENT ON COLUMN normalization_backfills.cut_over_at IS 'When the cutover finished.';
+
+-- normalized_event_versions: re-normalized rows staged by a backfill until cutover
+CREATE TABLE IF NOT EXISTS normalized_event_versions (
+  raw_event_id           uuid NOT NULL REFERENCES raw_events(raw_event_id) ON UPDATE RESTRICT ON DELETE CASCADE,
+  normalizer_version     integer NOT NULL,
+  backfill_id            uuid NOT NULL REFERENCES normalization_backfills(backfill_id) ON UPDATE RESTRICT ON DELETE CASCADE,
+  normalized_at          timestamptz NOT NULL DEFAULT now(),
+  observed_at            timestamptz NULL,
+  source_type            event_source_type NOT NULL,
+  event_kind             text NOT NULL,
+  event_subkind          text NULL,
+  severity               severity_level NOT NULL,
+  attributes             jsonb NULL,
+  deterministic_key      bytea NOT NULL,
+  PRIMARY KEY (raw_event_id, normalizer_version),
+  CONSTRAINT normalized_event_versions_det_key_len_chk CHECK (octet_length(deterministic_key) IN (16, 20, 32, 64))
+);
+
+COMMENT ON TABLE normalized_event_versions IS
+'Purpose: Staging area of a normalization backfill - the new version of each re-normalized event, kept beside the live normalized_events row until cutover copies the derived fields in place (normalized_event_id, source ids and references unchanged) and removes it.\n'
+'Writing module(s): Normalization backfill (core/normalization_worker/backfill.py).\n'
+'Reading module(s): Normalization backfill (cutover), operators comparing versions before cutover.\n'
+'Retention expectation: short (rows exist only between staging and cutover).';
+
+COMMENT ON COLUMN normalized_event_versions.raw_event_id IS 'Re-normalized raw event.';
+COMMENT ON COLUMN normalized_event_versions.normalizer_version IS 'Normalizer version that produced the row.';
+COMMENT ON COLUMN normalized_event_versions.backfill_id IS 'Backfill that staged the row.';
+COMMENT ON COLUMN normalized_event_versions.normalized_at IS 'When the row was re-normalized (becomes normalized_events.normalized_at at cutover).';
+COMMENT ON COLUMN normalized_event_versions.observed_at IS 'As normalized_events.observed_at, from this normalizer version.';
+COMMENT ON COLUMN normalized_event_versions.source_type IS 'As normalized_events.source_type, from this normalizer version.';
+COMMENT ON COLUMN normalized_event_versions.event_kind IS 'As normalized_events.event_kind, from this normalizer version.';
+COMMENT ON COLUMN normalized_event_versions.event_subkind IS 'As normalized_events.event_subkind, from this normalizer version.';
+COMMENT ON COLUMN normalized_event_versions.severity IS 'As normalized_events.severity, from this normalizer version.';
+COMMENT ON COLUMN normalized_event_versions.attributes IS 'As normalized_events.attributes, from this normalizer version.';
+COMMENT ON COLUMN normalized_event_versions.deterministic_key IS 'Deterministic key as computed by this normalizer version.';
+
+CREATE INDEX IF NOT EXISTS idx_normalized_event_versions_backfill ON normalized_event_versions (backfill_id);
+
 -- ============================================================================
 -- C. Correlation & Detection (REQUIRED)
 -- ============================================================================
@@ -1136,6 +2312,111 @@ CREATE INDEX IF NOT EXISTS idx_correlation_graph_dst_entity ON correlation_graph
 CREATE INDEX IF NOT EXISTS idx_correlation_graph_evidence_event ON correlation_graph (evidence_event_id);
 CREATE INDEX IF NOT EXISTS idx_correlation_graph_det_key ON correlation_graph (deterministic_key);
 
+-- detection_suppressions: signed, expiring rules that store matching detections without raising their alert
+CREATE TABLE IF NOT EXISTS detection_suppressions (
+  suppression_id         uuid PRIMARY KEY,
+  name                   text NOT NULL,
+  rule_json              text NOT NULL,
+  rule_sha256            bytea NOT NULL,
+  signature              bytea NOT NULL,
+  signer_key             bytea NOT NULL,
+  reason                 text NOT NULL,
+  created_by             text NOT NULL,
+  created_at             timestamptz NOT NULL DEFAULT now(),
+  expires_at             timestamptz NOT NULL,
+  expired_at             timestamptz NULL,
+  expired_by             text NULL,
+  expire_reason          text NULL,
+  hit_count              bigint NOT NULL DEFAULT 0,
+  last_hit_at            timestamptz NULL,
+  CONSTRAINT detection_suppressions_rule_sha256_uniq UNIQUE (rule_sha256),
+  CONSTRAINT detection_suppressions_rule_sha256_len_chk CHECK (octet_length(rule_sha256) = 32),
+  CONSTRAINT detection_suppressions_signature_len_chk CHECK (octet_length(signature) = 64),
+  CONSTRAINT detection_suppressions_signer_key_len_chk CHECK (octet_length(signer_key) = 32),
+  CONSTRAINT detection_suppressions_reason_chk CHECK (length(btrim(reason)) > 0),
+  CONSTRAINT detection_suppressions_expire_chk CHECK ((expired_at IS NULL) = (expired_by IS NULL)),
+  CONSTRAINT detection_suppressions_hit_count_chk CHECK (hit_count >= 0)
+);
+
+COMMENT ON TABLE detection_suppressions IS
+'Purpose: Signed detection suppression rules; a detection matched by an active rule is stored with suppression_id set and raises no alert (detection.created webhook).\n'
+'Writing module(s): Core Engine ingestion (admin API; hit_count/last_hit_at in the detection transaction).\n'
+'Reading module(s): Core Engine ingestion (detection insert, admin API), UI.\n'
+'Retention expectation: long (expired rules are kept as history).';
+
+COMMENT ON COLUMN detection_suppressions.suppression_id IS 'Primary key.';
+COMMENT ON COLUMN detection_suppressions.name IS 'Rule name from the signed document.';
+COMMENT ON COLUMN detection_suppressions.rule_json IS 'Rule document (match criteria, reason, created_by, expires_at) exactly as signed.';
+COMMENT ON COLUMN detection_suppressions.rule_sha256 IS 'SHA-256 of rule_json; unique, so a document cannot be submitted twice.';
+COMMENT ON COLUMN detection_suppressions.signature IS 'Ed25519 signature over rule_json; re-verified on every match.';
+COMMENT ON COLUMN detection_suppressions.signer_key IS 'Ed25519 public key that signed the rule (trusted via RANSOMEYE_INGEST_SUPPRESSION_SIGNERS).';
+COMMENT ON COLUMN detection_suppressions.reason IS 'Why the matched pattern is benign (ticket / change reference).';
+COMMENT ON COLUMN detection_suppressions.created_by IS 'Operator named in the signed document.';
+COMMENT ON COLUMN detection_suppressions.created_at IS 'When the rule was submitted.';
+COMMENT ON COLUMN detection_suppressions.expires_at IS 'End of the rule (signed; at most 90 days after submission).';
+COMMENT ON COLUMN detection_suppressions.expired_at IS 'When the rule was expired early (NULL unless expired through the admin API).';
+COMMENT ON COLUMN detection_suppressions.expired_by IS 'Operator who expired the rule early.';
+COMMENT ON COLUMN detection_suppressions.expire_reason IS 'Why the rule was expired early.';
+COMMENT ON COLUMN detection_suppressions.hit_count IS 'Detections suppressed by this rule.';
+COMMENT ON COLUMN detection_suppressions.last_hit_at IS 'When the rule last suppressed a detection.';
+
+CREATE INDEX IF NOT EXISTS idx_detection_suppressions_active ON detection_suppressions (expires_at) WHERE expired_at IS NULL;
+
+-- maintenance_windows: scoped time ranges that suppress or downgrade expected detections during patch work
+CREATE TABLE IF NOT EXISTS maintenance_windows (
+  window_id              uuid PRIMARY KEY,
+  scope_kind             text NOT NULL,
+  scope_value            text NOT NULL,
+  starts_at              timestamptz NOT NULL,
+  ends_at                timestamptz NOT NULL,
+  action                 text NOT NULL,
+  downgrade_to           severity_level NULL,
+  classes                jsonb NOT NULL,
+  reason                 text NOT NULL,
+  created_by             text NOT NULL,
+  created_at             timestamptz NOT NULL DEFAULT now(),
+  cancelled_at           timestamptz NULL,
+  cancelled_by           text NULL,
+  cancel_reason          text NULL,
+  suppressed_count       bigint NOT NULL DEFAULT 0,
+  downgraded_count       bigint NOT NULL DEFAULT 0,
+  last_hit_at            timestamptz NULL,
+  CONSTRAINT maintenance_windows_scope_kind_chk CHECK (scope_kind IN ('host', 'zone', 'tenant')),
+  CONSTRAINT maintenance_windows_action_chk CHECK (action IN ('suppress', 'downgrade')),
+  CONSTRAINT maintenance_windows_downgrade_chk CHECK ((action = 'downgrade') = (downgrade_to IS NOT NULL)),
+  CONSTRAINT maintenance_windows_range_chk CHECK (ends_at > starts_at),
+  CONSTRAINT maintenance_windows_classes_chk CHECK (jsonb_typeof(classes) = 'array' AND jsonb_array_length(classes) > 0),
+  CONSTRAINT maintenance_windows_reason_chk CHECK (length(btrim(reason)) > 0),
+  CONSTRAINT maintenance_windows_cancel_chk CHECK ((cancelled_at IS NULL) = (cancelled_by IS NULL)),
+  CONSTRAINT maintenance_windows_counts_chk CHECK (suppressed_count >= 0 AND downgraded_count >= 0)
+);
+
+COMMENT ON TABLE maintenance_windows IS
+'Purpose: Maintenance windows; a detection in scope and in a configured class while the window is active is stored with maintenance_window_id set and either raises no alert (suppress) or is alerted at a lower severity (downgrade).\n'
+'Writing module(s): Core Engine ingestion (admin API; hit counts in the detection transaction).\n'
+'Reading module(s): Core Engine ingestion (detection insert, admin API and report), UI.\n'
+'Retention expectation: long (ended windows are kept as history).';
+
+COMMENT ON COLUMN maintenance_windows.window_id IS 'Primary key.';
+COMMENT ON COLUMN maintenance_windows.scope_kind IS 'What the window covers: host (agent component identity or FQDN), zone (topology zone) or tenant (agents.tenant_id).';
+COMMENT ON COLUMN maintenance_windows.scope_value IS 'Host, zone id or tenant id covered.';
+COMMENT ON COLUMN maintenance_windows.starts_at IS 'Start of the window.';
+COMMENT ON COLUMN maintenance_windows.ends_at IS 'End of the window (at most 7 days after starts_at).';
+COMMENT ON COLUMN maintenance_windows.action IS 'suppress (stored, no alert) or downgrade (alerted at downgrade_to).';
+COMMENT ON COLUMN maintenance_windows.downgrade_to IS 'Severity downgraded detections are stored and alerted at; set exactly for downgrade windows.';
+COMMENT ON COLUMN maintenance_windows.classes IS 'Detection classes the window applies to: array of {detection_engine, detection_name, detection_category}, every field present must equal the detection''s.';
+COMMENT ON COLUMN maintenance_windows.reason IS 'Why the activity is expected (change reference).';
+COMMENT ON COLUMN maintenance_windows.created_by IS 'Operator who opened the window.';
+COMMENT ON COLUMN maintenance_windows.created_at IS 'When the window was opened.';
+COMMENT ON COLUMN maintenance_windows.cancelled_at IS 'When the window was ended early (NULL unless cancelled through the admin API).';
+COMMENT ON COLUMN maintenance_windows.cancelled_by IS 'Operator who ended the window early.';
+COMMENT ON COLUMN maintenance_windows.cancel_reason IS 'Why the window was ended early.';
+COMMENT ON COLUMN maintenance_windows.suppressed_count IS 'Detections suppressed by this window.';
+COMMENT ON COLUMN maintenance_windows.downgraded_count IS 'Detections downgraded by this window.';
+COMMENT ON COLUMN maintenance_windows.last_hit_at IS 'When the window last suppressed or downgraded a detection.';
+
+CREATE INDEX IF NOT EXISTS idx_maintenance_windows_active ON maintenance_windows (ends_at) WHERE cancelled_at IS NULL;
+
 CREATE TABLE IF NOT EXISTS detection_results (
   detection_id           uuid PRIMARY KEY DEFAULT gen_random_uuid(),
   created_at             timestamptz NOT NULL DEFAULT now(),
@@ -1153,13 +2434,18 @@ CREATE TABLE IF NOT EXISTS detection_results (
   reasoning              text NULL,
   artifacts              jsonb NULL,
   deterministic_key      bytea NOT NULL,
+  source_expired_at      timestamptz NULL,
+  suppression_id         uuid NULL REFERENCES detection_suppressions(suppression_id) ON UPDATE RESTRICT ON DELETE RESTRICT,
+  maintenance_window_id  uuid NULL REFERENCES maintenance_windows(window_id) ON UPDATE RESTRICT ON DELETE RESTRICT,
+  original_severity      severity_level NULL,
+  CONSTRAINT detection_results_original_severity_chk CHECK (original_severity IS NULL OR maintenance_window_id IS NOT NULL),
   CONSTRAINT detection_results_conf_chk CHECK (confidence >= 0.0 AND confidence <= 1.0),
   CONSTRAINT detection_results_det_key_len_chk CHECK (octet_length(deterministic_key) IN (16, 20, 32, 64))
 );
 
 COMMENT ON TABLE detection_results IS
 'Purpose: Detection outputs (rules/ML/correlation-based) tied to normalized events/entities with confidence and MITRE mapping.\n'
-'Writing module(s): Correlation Engine, AI/ML pipeline, Alert Engine.\n'
+'Writing module(s): Correlation Engine, AI/ML pipeline, Alert Engine, Core Engine ingestion (ingest rate budget violations, suppression_id, maintenance_window_id/original_severity), retention orphan GC (source_expired_at).\n'
 'Reading module(s): Policy Engine, Response Engine, UI, Forensics, Validator.\n'
 'Retention expectation: long.';
 
@@ -1179,12 +2465,383 @@ COMMENT ON COLUMN detection_results.score IS 'Optional raw model/rule score for
 COMMENT ON COLUMN detection_results.reasoning IS 'Optional human-readable reasoning summary.';
 COMMENT ON COLUMN detection_results.artifacts IS 'Optional structured artifacts (IOCs, paths, snippets) as JSONB.';
 COMMENT ON COLUMN detection_results.deterministic_key IS 'Deterministic key derived from engine+event/entity+name for deduplication.';
+COMMENT ON COLUMN detection_results.source_expired_at IS 'Set by the retention orphan GC when the correlation run (correlation_run_id) has been purged from correlation_graph; the detection is kept under its own policy.';
+COMMENT ON COLUMN detection_results.suppression_id IS 'detection_suppressions rule that matched at insert; the detection raised no alert. NULL when not suppressed.';
+COMMENT ON COLUMN detection_results.maintenance_window_id IS 'maintenance_windows window that suppressed (original_severity NULL) or downgraded the detection at insert. NULL outside maintenance.';
+COMMENT ON COLUMN detection_results.original_severity IS 'Severity the detection was raised at before a maintenance window downgraded it; severity holds the downgraded level.';
 
 CREATE INDEX IF NOT EXISTS idx_detection_results_created_at ON detection_results (created_at);
 CREATE INDEX IF NOT EXISTS idx_detection_results_norm_event ON detection_results (normalized_event_id);
 CREATE INDEX IF NOT EXISTS idx_detection_results_entity ON detection_results (primary_entity_id);
 CREATE INDEX IF NOT EXISTS idx_detection_results_corr_run ON detection_results (correlation_run_id);
 CREATE INDEX IF NOT EXISTS idx_detection_results_det_key ON detection_results (deterministic_key);
+CREATE INDEX IF NOT EXISTS idx_detection_results_suppression ON detection_results (suppression_id) WHERE suppression_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_detection_results_maintenance ON detection_results (maintenance_window_id, created_at) WHERE maintenance_window_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_detection_results_incident ON detection_results ((lower(artifacts->>'incident_id')));
+
+-- pcap_captures: bounded DPI probe captures of hosts with critical detections
+CREATE TABLE IF NOT EXISTS pcap_captures (
+  capture_id             uuid PRIMARY KEY,
+  detection_id           uuid NULL REFERENCES detection_results(detection_id) ON UPDATE RESTRICT ON DELETE SET NULL,
+  incident_id            uuid NULL,
+  host_ip                inet NOT NULL,
+  bpf_filter             text NOT NULL,
+  duration_secs          integer NOT NULL,
+  max_bytes              bigint NOT NULL,
+  snaplen                integer NOT NULL,
+  status                 text NOT NULL DEFAULT 'requested',
+  requested_at           timestamptz NOT NULL DEFAULT now(),
+  probe_agent_id         uuid NULL REFERENCES agents(agent_id) ON UPDATE RESTRICT ON DELETE RESTRICT,
+  claimed_at             timestamptz NULL,
+  finished_at            timestamptz NULL,
+  pcap_sha256            bytea NULL,
+  pcap_bytes             bigint NULL,
+  failure_reason         text NULL,
+  CONSTRAINT pcap_captures_status_chk CHECK (status IN ('requested', 'capturing', 'uploaded', 'failed', 'expired')),
+  CONSTRAINT pcap_captures_bounds_chk CHECK (duration_secs > 0 AND max_bytes > 0 AND snaplen > 0),
+  CONSTRAINT pcap_captures_claimed_chk CHECK ((status = 'requested') = (claimed_at IS NULL) OR status = 'expired'),
+  CONSTRAINT pcap_captures_uploaded_chk CHECK ((status = 'uploaded') = (pcap_sha256 IS NOT NULL)),
+  CONSTRAINT pcap_captures_sha256_len_chk CHECK (pcap_sha256 IS NULL OR octet_length(pcap_sha256) = 32)
+);
+
+COMMENT ON TABLE pcap_captures IS
+'Purpose: Packet capture requests queued for critical detections, claimed by DPI probes over the control channel, and the pcaps they uploaded.\n'
+'Writing module(s): Core Engine ingestion (queue with the detection, probe claim/upload/fail, expiry).\n'
+'Reading module(s): Core Engine ingestion (probe control channel, admin API), UI, Forensics.\n'
+'Retention expectation: long (the pcap itself is sealed in the reporting evidence store).';
+
+COMMENT ON COLUMN pcap_captures.capture_id IS 'Primary key; names the spooled pcap and its evidence item.';
+COMMENT ON COLUMN pcap_captures.detection_id IS 'Triggering detection; NULL once retention purged it.';
+COMMENT ON COLUMN pcap_captures.incident_id IS 'Incident named by the detection artifacts (optional).';
+COMMENT ON COLUMN pcap_captures.host_ip IS 'Captured host address.';
+COMMENT ON COLUMN pcap_captures.bpf_filter IS 'libpcap filter handed to the probe (host <address>).';
+COMMENT ON COLUMN pcap_captures.duration_secs IS 'Capture length in seconds.';
+COMMENT ON COLUMN pcap_captures.max_bytes IS 'Upper bound on the pcap file size.';
+COMMENT ON COLUMN pcap_captures.snaplen IS 'Bytes captured per packet.';
+COMMENT ON COLUMN pcap_captures.status IS 'requested, capturing (claimed by a probe), uploaded, failed or expired.';
+COMMENT ON COLUMN pcap_captures.requested_at IS 'When the capture was queued.';
+COMMENT ON COLUMN pcap_captures.probe_agent_id IS 'DPI probe (agents.agent_id) that claimed the capture.';
+COMMENT ON COLUMN pcap_captures.claimed_at IS 'When the probe claimed the capture; the capture deadline is claimed_at + duration_secs.';
+COMMENT ON COLUMN pcap_captures.finished_at IS 'When the pcap was uploaded, or the capture failed or expired.';
+COMMENT ON COLUMN pcap_captures.pcap_sha256 IS 'SHA-256 of the uploaded pcap (uploaded only).';
+COMMENT ON COLUMN pcap_captures.pcap_bytes IS 'Size of the uploaded pcap in bytes.';
+COMMENT ON COLUMN pcap_captures.failure_reason IS 'Reason reported by the probe for a failed capture.';
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_pcap_captures_active_host ON pcap_captures (host_ip) WHERE status IN ('requested', 'capturing');
+CREATE INDEX IF NOT EXISTS idx_pcap_captures_status_requested ON pcap_captures (status, requested_at);
+
+-- memory_acquisitions: operator-requested memory images taken by Linux agents
+CREATE TABLE IF NOT EXISTS memory_acquisitions (
+  acquisition_id         uuid PRIMARY KEY,
+  agent_id               uuid NOT NULL REFERENCES agents(agent_id) ON UPDATE RESTRICT ON DELETE RESTRICT,
+  incident_id            uuid NULL,
+  scope                  text NOT NULL,
+  pid                    integer NULL,
+  reason                 text NOT NULL,
+  requested_by           text NOT NULL,
+  approved_by            text NOT NULL,
+  max_bytes              bigint NOT NULL,
+  chunk_bytes            bigint NOT NULL,
+  status                 text NOT NULL DEFAULT 'requested',
+  requested_at           timestamptz NOT NULL DEFAULT now(),
+  claimed_at             timestamptz NULL,
+  last_activity_at       timestamptz NULL,
+  finished_at            timestamptz NULL,
+  chunks_received        integer NOT NULL DEFAULT 0,
+  bytes_received         bigint NOT NULL DEFAULT 0,
+  image_sha256           bytea NULL,
+  tool                   text NULL,
+  failure_reason         text NULL,
+  CONSTRAINT memory_acquisitions_scope_chk CHECK ((scope = 'process' AND pid > 0) OR (scope = 'full' AND pid IS NULL)),
+  CONSTRAINT memory_acquisitions_status_chk CHECK (status IN ('requested', 'acquiring', 'acquired', 'failed', 'expired')),
+  CONSTRAINT memory_acquisitions_two_person_chk CHECK (lower(btrim(requested_by)) <> lower(btrim(approved_by))),
+  CONSTRAINT memory_acquisitions_bounds_chk CHECK (max_bytes > 0 AND chunk_bytes > 0 AND bytes_received <= max_bytes),
+  CONSTRAINT memory_acquisitions_acquired_chk CHECK ((status = 'acquired') = (image_sha256 IS NOT NULL)),
+  CONSTRAINT memory_acquisitions_sha256_len_chk CHECK (image_sha256 IS NULL OR octet_length(image_sha256) = 32)
+);
+
+COMMENT ON TABLE memory_acquisitions IS
+'Purpose: Memory acquisition requests raised by operators (policy-gated, two-person), claimed by the named Linux agent, and the images it streamed in chunks.\n'
+'Writing module(s): Core Engine ingestion (admin request, agent claim/chunk/complete/fail, expiry).\n'
+'Reading module(s): Core Engine ingestion (agent control channel, admin API), UI, Forensics.\n'
+'Retention expectation: long (chain of custody; the image itself is sealed in the reporting evidence store).';
+
+COMMENT ON COLUMN memory_acquisitions.acquisition_id IS 'Primary key; names the spooled chunks, manifest and evidence item.';
+COMMENT ON COLUMN memory_acquisitions.agent_id IS 'Linux agent (agents.agent_id) asked for the image; only it may claim the request.';
+COMMENT ON COLUMN memory_acquisitions.incident_id IS 'Incident the image is taken for (optional).';
+COMMENT ON COLUMN memory_acquisitions.scope IS 'process (one pid) or full (all physical memory).';
+COMMENT ON COLUMN memory_acquisitions.pid IS 'Process to image (process scope only).';
+COMMENT ON COLUMN memory_acquisitions.reason IS 'Case or incident reference justifying the acquisition.';
+COMMENT ON COLUMN memory_acquisitions.requested_by IS 'Operator who requested the acquisition.';
+COMMENT ON COLUMN memory_acquisitions.approved_by IS 'Second operator who approved it (never the requester).';
+COMMENT ON COLUMN memory_acquisitions.max_bytes IS 'Largest image accepted (policy at request time).';
+COMMENT ON COLUMN memory_acquisitions.chunk_bytes IS 'Upload chunk size handed to the agent.';
+COMMENT ON COLUMN memory_acquisitions.status IS 'requested, acquiring (claimed by the agent), acquired, failed or expired.';
+COMMENT ON COLUMN memory_acquisitions.requested_at IS 'When the acquisition was requested.';
+COMMENT ON COLUMN memory_acquisitions.claimed_at IS 'When the agent claimed the acquisition.';
+COMMENT ON COLUMN memory_acquisitions.last_activity_at IS 'Claim or last chunk; an acquisition idle too long expires.';
+COMMENT ON COLUMN memory_acquisitions.finished_at IS 'When the image was verified, or the acquisition failed or expired.';
+COMMENT ON COLUMN memory_acquisitions.chunks_received IS 'Chunks recorded in memory_acquisition_chunks.';
+COMMENT ON COLUMN memory_acquisitions.bytes_received IS 'Bytes received so far.';
+COMMENT ON COLUMN memory_acquisitions.image_sha256 IS 'SHA-256 of the whole image, verified by ingest (acquired only).';
+COMMENT ON COLUMN memory_acquisitions.tool IS 'Acquisition tool and version reported by the agent.';
+COMMENT ON COLUMN memory_acquisitions.failure_reason IS 'Reason reported by the agent for a failed acquisition.';
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_memory_acquisitions_active_agent ON memory_acquisitions (agent_id) WHERE status IN ('requested', 'acquiring');
+CREATE INDEX IF NOT EXISTS idx_memory_acquisitions_status_requested ON memory_acquisitions (status, requested_at);
+
+-- memory_acquisition_chunks: per-chunk hashes of a streamed memory image
+CREATE TABLE IF NOT EXISTS memory_acquisition_chunks (
+  acquisition_id         uuid NOT NULL REFERENCES memory_acquisitions(acquisition_id) ON UPDATE RESTRICT ON DELETE RESTRICT,
+  chunk_index            integer NOT NULL,
+  sha256                 bytea NOT NULL,
+  bytes                  bigint NOT NULL,
+  received_at            timestamptz NOT NULL DEFAULT now(),
+  PRIMARY KEY (acquisition_id, chunk_index),
+  CONSTRAINT memory_acquisition_chunks_index_chk CHECK (chunk_index >= 0),
+  CONSTRAINT memory_acquisition_chunks_bytes_chk CHECK (bytes > 0),
+  CONSTRAINT memory_acquisition_chunks_sha256_len_chk CHECK (octet_length(sha256) = 32)
+);
+
+COMMENT ON TABLE memory_acquisition_chunks IS
+'Purpose: SHA-256 and size of every chunk of a memory image as it arrived, in image order (append-only).\n'
+'Writing module(s): Core Engine ingestion (agent chunk upload).\n'
+'Reading module(s): Core Engine ingestion (completion verification), Forensics.\n'
+'Retention expectation: with memory_acquisitions.';
+
+COMMENT ON COLUMN memory_acquisition_chunks.acquisition_id IS 'FK to memory_acquisitions.';
+COMMENT ON COLUMN memory_acquisition_chunks.chunk_index IS 'Zero-based position of the chunk in the image.';
+COMMENT ON COLUMN memory_acquisition_chunks.sha256 IS 'SHA-256 of the chunk, checked on arrival.';
+COMMENT ON COLUMN memory_acquisition_chunks.bytes IS 'Chunk size in bytes.';
+COMMENT ON COLUMN memory_acquisition_chunks.received_at IS 'When ingest recorded the chunk.';
+
+DROP TRIGGER IF EXISTS trg_memory_acquisition_chunks_no_update ON memory_acquisition_chunks;
+CREATE TRIGGER trg_memory_acquisition_chunks_no_update
+BEFORE UPDATE OR DELETE ON memory_acquisition_chunks
+FOR EACH ROW EXECUTE FUNCTION prevent_update_delete();
+
+-- agent_log_segments: sealed, hash-chained log segments shipped by Linux agents
+CREATE TABLE IF NOT EXISTS agent_log_segments (
+  segment_id               uuid PRIMARY KEY,
+  agent_id                 uuid NOT NULL REFERENCES agents(agent_id) ON UPDATE RESTRICT ON DELETE RESTRICT,
+  chain_id                 text NOT NULL,
+  boot_id                  text NOT NULL,
+  segment_index            bigint NOT NULL,
+  first_line               bigint NOT NULL,
+  line_count               integer NOT NULL,
+  prev_hash                bytea NOT NULL,
+  head_hash                bytea NOT NULL,
+  sealed_at                timestamptz NOT NULL,
+  segments_dropped_before  bigint NOT NULL DEFAULT 0,
+  signer_key               bytea NOT NULL,
+  signature                bytea NOT NULL,
+  continuity               text NOT NULL,
+  received_at              timestamptz NOT NULL DEFAULT now(),
+  CONSTRAINT agent_log_segments_position_uniq UNIQUE (agent_id, chain_id, segment_index),
+  CONSTRAINT agent_log_segments_position_chk CHECK (segment_index >= 0 AND first_line >= 0 AND line_count > 0 AND segments_dropped_before >= 0),
+  CONSTRAINT agent_log_segments_hash_len_chk CHECK (octet_length(prev_hash) = 32 AND octet_length(head_hash) = 32),
+  CONSTRAINT agent_log_segments_key_len_chk CHECK (octet_length(signer_key) = 32 AND octet_length(signature) = 64),
+  CONSTRAINT agent_log_segments_continuity_chk CHECK (continuity IN ('genesis', 'linked', 'gap'))
+);
+
+COMMENT ON TABLE agent_log_segments IS
+'Purpose: Sealed log segments of Linux agents (hash-chained lines, Ed25519 seal) and how each links to the segments received before it (append-only; the lines are in the evidence store).\n'
+'Writing module(s): Core Engine ingestion (agent log segment upload).\n'
+'Reading module(s): Core Engine ingestion (continuity checks), Forensics.\n'
+'Retention expectation: long (with the evidence bundles of the segments).';
+
+COMMENT ON COLUMN agent_log_segments.segment_id IS 'Primary key; names the spooled segment and its evidence item.';
+COMMENT ON COLUMN agent_log_segments.agent_id IS 'Agent (agents.agent_id) that shipped the segment.';
+COMMENT ON COLUMN agent_log_segments.chain_id IS 'Log chain of one agent start.';
+COMMENT ON COLUMN agent_log_segments.boot_id IS 'Kernel boot id of the host when the chain started.';
+COMMENT ON COLUMN agent_log_segments.segment_index IS 'Zero-based position of the segment in its chain.';
+COMMENT ON COLUMN agent_log_segments.first_line IS 'Zero-based chain position of the first line.';
+COMMENT ON COLUMN agent_log_segments.line_count IS 'Lines in the segment.';
+COMMENT ON COLUMN agent_log_segments.prev_hash IS 'Hash the first line links to: head of the previous segment, or the chain genesis.';
+COMMENT ON COLUMN agent_log_segments.head_hash IS 'Hash of the last line; verified against the lines on arrival.';
+COMMENT ON COLUMN agent_log_segments.sealed_at IS 'When the agent sealed the segment (agent clock).';
+COMMENT ON COLUMN agent_log_segments.segments_dropped_before IS 'Segments the agent reported dropping just before this one.';
+COMMENT ON COLUMN agent_log_segments.signer_key IS 'Ed25519 public key of the agent identity that signed the seal.';
+COMMENT ON COLUMN agent_log_segments.signature IS 'Ed25519 signature of the seal digest, verified on arrival.';
+COMMENT ON COLUMN agent_log_segments.continuity IS 'genesis (segment 0), linked (follows the stored previous segment) or gap (previous segment never arrived).';
+COMMENT ON COLUMN agent_log_segments.received_at IS 'When ingest recorded the segment.';
+
+DROP TRIGGER IF EXISTS trg_agent_log_segments_no_update ON agent_log_segments;
+CREATE TRIGGER trg_agent_log_segments_no_update
+BEFORE UPDATE OR DELETE ON agent_log_segments
+FOR EACH ROW EXECUTE FUNCTION prevent_update_delete();
+
+-- sandbox_submissions: files sent (by hash or as samples) to the detonation sandbox, and verdicts
+CREATE TABLE IF NOT EXISTS sandbox_submissions (
+  submission_id          uuid PRIMARY KEY,
+  sha256                 bytea NOT NULL,
+  file_path              text NULL,
+  file_size              bigint NULL,
+  agent_id               uuid NULL REFERENCES agents(agent_id) ON UPDATE RESTRICT ON DELETE RESTRICT,
+  incident_id            uuid NULL,
+  source                 text NOT NULL,
+  submitted_by           text NOT NULL,
+  reason                 text NULL,
+  mode                   text NOT NULL,
+  status                 text NOT NULL DEFAULT 'queued',
+  queued_at              timestamptz NOT NULL DEFAULT now(),
+  submitted_at           timestamptz NULL,
+  finished_at            timestamptz NULL,
+  sandbox_task_id        bigint NULL,
+  score                  double precision NULL,
+  verdict                text NULL,
+  report_sha256          bytea NULL,
+  detection_id           uuid NULL REFERENCES detection_results(detection_id) ON UPDATE RESTRICT ON DELETE SET NULL,
+  failure_reason         text NULL,
+  CONSTRAINT sandbox_submissions_source_chk CHECK (source IN ('agent', 'operator')),
+  CONSTRAINT sandbox_submissions_mode_chk CHECK (mode IN ('hash', 'sample')),
+  CONSTRAINT sandbox_submissions_status_chk CHECK (status IN ('queued', 'submitted', 'reported', 'not_found', 'failed')),
+  CONSTRAINT sandbox_submissions_verdict_chk CHECK (verdict IS NULL OR verdict IN ('clean', 'suspicious', 'malicious')),
+  CONSTRAINT sandbox_submissions_reported_chk CHECK (
+    (status = 'reported') = (verdict IS NOT NULL AND score IS NOT NULL AND report_sha256 IS NOT NULL)
+  ),
+  CONSTRAINT sandbox_submissions_file_size_chk CHECK (file_size IS NULL OR file_size >= 0),
+  CONSTRAINT sandbox_submissions_sha256_len_chk CHECK (octet_length(sha256) = 32),
+  CONSTRAINT sandbox_submissions_report_sha256_len_chk CHECK (report_sha256 IS NULL OR octet_length(report_sha256) = 32)
+);
+
+COMMENT ON TABLE sandbox_submissions IS
+'Purpose: Suspicious files submitted to the detonation sandbox (policy-gated: by hash, or as samples), their sandbox task and verdict.\n'
+'Writing module(s): Core Engine ingestion (agent sample upload, admin submission, sandbox worker).\n'
+'Reading module(s): Core Engine ingestion (sandbox worker, admin API), UI, Forensics.\n'
+'Retention expectation: long (the report itself is sealed in the reporting evidence store).';
+
+COMMENT ON COLUMN sandbox_submissions.submission_id IS 'Primary key; names the spooled report, manifest and evidence item.';
+COMMENT ON COLUMN sandbox_submissions.sha256 IS 'SHA-256 of the file.';
+COMMENT ON COLUMN sandbox_submissions.file_path IS 'Path of the file on the host it was observed on (optional).';
+COMMENT ON COLUMN sandbox_submissions.file_size IS 'File size in bytes (optional).';
+COMMENT ON COLUMN sandbox_submissions.agent_id IS 'Agent (agents.agent_id) the file was observed on (optional for operator submissions).';
+COMMENT ON COLUMN sandbox_submissions.incident_id IS 'Incident the file belongs to (optional).';
+COMMENT ON COLUMN sandbox_submissions.source IS 'agent (uploaded by an agent) or operator (admin API).';
+COMMENT ON COLUMN sandbox_submissions.submitted_by IS 'Agent id or operator who submitted the file.';
+COMMENT ON COLUMN sandbox_submissions.reason IS 'Why the file was submitted (optional).';
+COMMENT ON COLUMN sandbox_submissions.mode IS 'hash (the sandbox is asked for an existing analysis) or sample (the file is uploaded).';
+COMMENT ON COLUMN sandbox_submissions.status IS 'queued, submitted (sandbox task running), reported, not_found (hash unknown to the sandbox) or failed.';
+COMMENT ON COLUMN sandbox_submissions.queued_at IS 'When the submission was accepted.';
+COMMENT ON COLUMN sandbox_submissions.submitted_at IS 'When the sandbox task was created or found.';
+COMMENT ON COLUMN sandbox_submissions.finished_at IS 'When the verdict was recorded, or the submission failed or was not found.';
+COMMENT ON COLUMN sandbox_submissions.sandbox_task_id IS 'Task id in the sandbox.';
+COMMENT ON COLUMN sandbox_submissions.score IS 'Sandbox score (0-10; reported only).';
+COMMENT ON COLUMN sandbox_submissions.verdict IS 'clean, suspicious or malicious, from the score and ingest thresholds (reported only).';
+COMMENT ON COLUMN sandbox_submissions.report_sha256 IS 'SHA-256 of the spooled sandbox report (reported only).';
+COMMENT ON COLUMN sandbox_submissions.detection_id IS 'Detection raised for a malicious verdict (optional FK to detection_results).';
+COMMENT ON COLUMN sandbox_submissions.failure_reason IS 'Why the submission failed.';
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_sandbox_submissions_active_sha256 ON sandbox_submissions (sha256) WHERE status IN ('queued', 'submitted', 'reported');
+CREATE INDEX IF NOT EXISTS idx_sandbox_submissions_status_queued ON sandbox_submissions (status, queued_at);
+
+-- yara_rule_packs: signed YARA rule packs distributed to Linux agents over the control channel
+CREATE TABLE IF NOT EXISTS yara_rule_packs (
+  pack_id                uuid PRIMARY KEY,
+  name                   text NOT NULL,
+  version                bigint NOT NULL,
+  document               text NOT NULL,
+  document_sha256        bytea NOT NULL,
+  signature              bytea NOT NULL,
+  signer_key             bytea NOT NULL,
+  rules_bytes            integer NOT NULL,
+  reason                 text NOT NULL,
+  created_by             text NOT NULL,
+  created_at             timestamptz NOT NULL DEFAULT now(),
+  CONSTRAINT yara_rule_packs_version_uniq UNIQUE (version),
+  CONSTRAINT yara_rule_packs_document_sha256_uniq UNIQUE (document_sha256),
+  CONSTRAINT yara_rule_packs_version_chk CHECK (version > 0),
+  CONSTRAINT yara_rule_packs_document_sha256_len_chk CHECK (octet_length(document_sha256) = 32),
+  CONSTRAINT yara_rule_packs_signature_len_chk CHECK (octet_length(signature) = 64),
+  CONSTRAINT yara_rule_packs_signer_key_len_chk CHECK (octet_length(signer_key) = 32),
+  CONSTRAINT yara_rule_packs_reason_chk CHECK (length(btrim(reason)) > 0)
+);
+
+COMMENT ON TABLE yara_rule_packs IS
+'Purpose: Signed YARA rule packs; the highest version is handed to Linux agents, which verify the signature themselves before scanning with it.\n'
+'Writing module(s): Core Engine ingestion (admin API).\n'
+'Reading module(s): Core Engine ingestion (agent YARA poll, admin API), UI.\n'
+'Retention expectation: long (superseded packs are kept as history; match events name the pack version).';
+
+COMMENT ON COLUMN yara_rule_packs.pack_id IS 'Primary key.';
+COMMENT ON COLUMN yara_rule_packs.name IS 'Pack name from the signed document.';
+COMMENT ON COLUMN yara_rule_packs.version IS 'Pack version from the signed document; each new pack must exceed every earlier one.';
+COMMENT ON COLUMN yara_rule_packs.document IS 'Pack document (name, version, rules, reason, created_by) exactly as signed.';
+COMMENT ON COLUMN yara_rule_packs.document_sha256 IS 'SHA-256 of document; unique, so a document cannot be submitted twice.';
+COMMENT ON COLUMN yara_rule_packs.signature IS 'Ed25519 signature over document; verified by ingest and again by every agent.';
+COMMENT ON COLUMN yara_rule_packs.signer_key IS 'Ed25519 public key that signed the pack (trusted via RANSOMEYE_INGEST_YARA_SIGNERS and AGENT_YARA_SIGNERS).';
+COMMENT ON COLUMN yara_rule_packs.rules_bytes IS 'Size of the YARA rule source in the document.';
+COMMENT ON COLUMN yara_rule_packs.reason IS 'Why the pack was issued (ticket / change reference).';
+COMMENT ON COLUMN yara_rule_packs.created_by IS 'Operator named in the signed document.';
+COMMENT ON COLUMN yara_rule_packs.created_at IS 'When the pack was submitted.';
+
+-- yara_scan_requests: operator-requested on-demand YARA scans, claimed by the named Linux agent
+CREATE TABLE IF NOT EXISTS yara_scan_requests (
+  scan_id                uuid PRIMARY KEY,
+  agent_id               uuid NOT NULL REFERENCES agents(agent_id) ON UPDATE RESTRICT ON DELETE RESTRICT,
+  paths                  text[] NOT NULL,
+  reason                 text NOT NULL,
+  requested_by           text NOT NULL,
+  status                 text NOT NULL DEFAULT 'requested',
+  requested_at           timestamptz NOT NULL DEFAULT now(),
+  claimed_at             timestamptz NULL,
+  CONSTRAINT yara_scan_requests_status_chk CHECK (status IN ('requested', 'claimed', 'expired')),
+  CONSTRAINT yara_scan_requests_claimed_chk CHECK ((status = 'claimed') = (claimed_at IS NOT NULL)),
+  CONSTRAINT yara_scan_requests_reason_chk CHECK (length(btrim(reason)) > 0)
+);
+
+COMMENT ON TABLE yara_scan_requests IS
+'Purpose: On-demand YARA scans requested by operators and handed to the named Linux agent on its next poll; matches arrive as yara_match events, scan summaries in agent_stats.\n'
+'Writing module(s): Core Engine ingestion (admin request, agent poll, expiry).\n'
+'Reading module(s): Core Engine ingestion (agent YARA poll, admin API), UI.\n'
+'Retention expectation: medium (request history).';
+
+COMMENT ON COLUMN yara_scan_requests.scan_id IS 'Primary key; carried in the scan summary and match events of the scan.';
+COMMENT ON COLUMN yara_scan_requests.agent_id IS 'Linux agent (agents.agent_id) asked to scan; only it may claim the request.';
+COMMENT ON COLUMN yara_scan_requests.paths IS 'Absolute paths to scan; empty means the paths configured on the agent.';
+COMMENT ON COLUMN yara_scan_requests.reason IS 'Case or incident reference justifying the scan.';
+COMMENT ON COLUMN yara_scan_requests.requested_by IS 'Operator who requested the scan.';
+COMMENT ON COLUMN yara_scan_requests.status IS 'requested, claimed (handed to the agent) or expired (not claimed in time).';
+COMMENT ON COLUMN yara_scan_requests.requested_at IS 'When the scan was requested.';
+COMMENT ON COLUMN yara_scan_requests.claimed_at IS 'When the agent claimed the scan.';
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_yara_scan_requests_pending_agent ON yara_scan_requests (agent_id) WHERE status = 'requested';
+
+-- update_bundles: signed offline update bundles applied to this deployment
+CREATE TABLE IF NOT EXISTS update_bundles (
+  bundle_id              uuid PRIMARY KEY,
+  version                bigint NOT NULL,
+  release                text NOT NULL,
+  manifest               jsonb NOT NULL,
+  manifest_sha256        text NOT NULL,
+  signer_key             text NOT NULL,
+  migrations_applied     integer NOT NULL DEFAULT 0,
+  staged_path            text NOT NULL,
+  applied_by             text NOT NULL,
+  applied_at             timestamptz NOT NULL DEFAULT now(),
+  CONSTRAINT update_bundles_version_uq UNIQUE (version),
+  CONSTRAINT update_bundles_version_chk CHECK (version > 0),
+  CONSTRAINT update_bundles_manifest_sha256_chk CHECK (manifest_sha256 ~ '^[0-9a-f]{64}$'),
+  CONSTRAINT update_bundles_signer_key_chk CHECK (signer_key ~ '^[0-9a-f]{64}$'),
+  CONSTRAINT update_bundles_migrations_chk CHECK (migrations_applied >= 0)
+);
+
+COMMENT ON TABLE update_bundles IS
+'Purpose: Offline (air-gapped) update bundles verified, staged and applied, one row per bundle version.\n'
+'Writing module(s): Orchestrator update bundle CLI (apply-bundle).\n'
+'Reading module(s): Orchestrator update bundle CLI (version check), installer, UI.\n'
+'Retention expectation: long (update provenance).';
+
+COMMENT ON COLUMN update_bundles.bundle_id IS 'Primary key; object_id of the UPDATE_BUNDLE_APPLIED audit row.';
+COMMENT ON COLUMN update_bundles.version IS 'Bundle version from the manifest; each applied bundle exceeds every earlier one.';
+COMMENT ON COLUMN update_bundles.release IS 'Release label from the manifest.';
+COMMENT ON COLUMN update_bundles.manifest IS 'Verified bundle manifest (artifacts with kind, path, size and SHA-256).';
+COMMENT ON COLUMN update_bundles.manifest_sha256 IS 'SHA-256 (hex) of manifest.json exactly as signed.';
+COMMENT ON COLUMN update_bundles.signer_key IS 'Hex Ed25519 public key that signed the manifest.';
+COMMENT ON COLUMN update_bundles.migrations_applied IS 'Schema migrations run in the apply transaction.';
+COMMENT ON COLUMN update_bundles.staged_path IS 'Directory the artifacts were staged in for the installer.';
+COMMENT ON COLUMN update_bundles.applied_by IS 'Operator who applied the bundle.';
+COMMENT ON COLUMN update_bundles.applied_at IS 'When the apply committed.';
 
 CREATE TABLE IF NOT EXISTS confidence_scores (
   confidence_score_id    uuid PRIMARY KEY DEFAULT gen_random_uuid(),
@@ -1440,12 +3097,13 @@ CREATE TABLE IF NOT EXISTS llm_requests (
   tool_spec              jsonb NULL,
   context_refs           jsonb NULL,
   offline_policy         text NULL,
+  source_expired_at      timestamptz NULL,
   CONSTRAINT llm_requests_prompt_sha256_len_chk CHECK (octet_length(prompt_sha256) = 32)
 );
 
 COMMENT ON TABLE llm_requests IS
 'Purpose: Store LLM request prompts and parameters for deterministic replay and auditability.\n'
-'Writing module(s): LLM module, SOC Copilot (Assistant), Incident Summarizer.\n'
+'Writing module(s): LLM module, SOC Copilot (Assistant), Incident Summarizer, retention orphan GC (source_expired_at).\n'
 'Reading module(s): Forensics, Validator, UI, Audit.\n'
 'Retention expectation: long.';
 
@@ -1468,11 +3126,13 @@ COMMENT ON COLUMN llm_requests.stop_sequences IS 'Optional stop sequences list (
 COMMENT ON COLUMN llm_requests.tool_spec IS 'Optional tool/function-call spec (JSONB).';
 COMMENT ON COLUMN llm_requests.context_refs IS 'Optional references to evidence/artifacts used as context (JSONB).';
 COMMENT ON COLUMN llm_requests.offline_policy IS 'Optional label describing offline/air-gapped constraints enforced for this request.';
+COMMENT ON COLUMN llm_requests.source_expired_at IS 'Set by the retention orphan GC when the correlation run (correlation_run_id) has been purged from correlation_graph.';
 
 CREATE INDEX IF NOT EXISTS idx_llm_requests_created_at ON llm_requests (created_at);
 CREATE INDEX IF NOT EXISTS idx_llm_requests_requester_component_id ON llm_requests (requester_component_id);
 CREATE INDEX IF NOT EXISTS idx_llm_requests_requester_agent_id ON llm_requests (requester_agent_id);
 CREATE INDEX IF NOT EXISTS idx_llm_requests_prompt_sha256 ON llm_requests (prompt_sha256);
+CREATE INDEX IF NOT EXISTS idx_llm_requests_corr_run ON llm_requests (correlation_run_id);
 
 CREATE TABLE IF NOT EXISTS llm_responses (
   llm_response_id        uuid PRIMARY KEY DEFAULT gen_random_uuid(),
@@ -1713,6 +3373,10 @@ CREATE INDEX IF NOT EXISTS idx_immutable_audit_created_at ON immutable_audit_log
 CREATE INDEX IF NOT EXISTS idx_immutable_audit_object ON immutable_audit_log (object_type, object_id);
 CREATE INDEX IF NOT EXISTS idx_immutable_audit_actor_component ON immutable_audit_log (actor_component_id);
 CREATE INDEX IF NOT EXISTS idx_immutable_audit_actor_agent ON immutable_audit_log (actor_agent_id);
+-- Audit review attestations are read back from the chain (GET /admin/audit-reviews)
+CREATE INDEX IF NOT EXISTS idx_immutable_audit_review_attestations ON immutable_audit_log (created_at) WHERE action = 'AUDIT_REVIEW_ATTESTED';
+-- Fork guard: each chain entry has at most one successor (writers serialize via advisory lock "REAUDIT")
+CREATE UNIQUE INDEX IF NOT EXISTS idx_immutable_audit_prev_audit_id_uniq ON immutable_audit_log (prev_audit_id);
 
 CREATE TRIGGER trg_immutable_audit_no_update
 BEFORE UPDATE OR DELETE ON immutable_audit_log
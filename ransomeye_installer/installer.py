@@ -301,7 +301,7 @@ RETURNING table_name;
 WITH last AS (
   SELECT audit_id, chain_hash_sha256
   FROM ransomeye.immutable_audit_log
-  ORDER BY created_at DESC
+  ORDER BY created_at DESC, audit_id DESC
   LIMIT 1
 ),
 payload AS (
@@ -400,6 +400,8 @@ RETURNING audit_id, chain_hash_sha256;
                 inserted_tables = [r[0] for r in cur.fetchall()]
 
                 ts_utc = datetime.now(timezone.utc).strftime("%Y-%m-%dT%H:%M:%SZ")
+                # Serialize audit chain extension with runtime writers (key "REAUDIT", AUDIT_CHAIN_LOCK_KEY in core/ingest/src/storage/postgres.rs)
+                cur.execute("SELECT pg_advisory_xact_lock(%s)", (0x52454155444954,))
                 cur.execute(sql_audit_insert, {"retention_days": retention_days, "ts_utc": ts_utc})
                 audit_row = cur.fetchone()
 